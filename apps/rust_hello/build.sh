@@ -0,0 +1,9 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+OUT_BIN="${1:-${SCRIPT_DIR}/RUSTHELL.BIN}"
+HELPER="${SCRIPT_DIR}/../../sdk/redux-sdk/cargo-redux/Cargo.toml"
+
+cd "${SCRIPT_DIR}"
+cargo run --quiet --manifest-path "${HELPER}" -- build --release --out "${OUT_BIN}"
@@ -0,0 +1,55 @@
+//! Console example for redux-sdk: arguments, files, config and HTTP.
+//!
+//! `linux runloop start /RUSTHELL.BIN [URL]`
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use redux_sdk::{config, fs, http, println, rt, time, Error};
+
+redux_sdk::main!(main);
+
+const NOTE_PATH: &str = "/RUSTHELL.TXT";
+const RUNS_KEY: &str = "apps.rust_hello.runs";
+
+fn main() -> redux_sdk::Result<()> {
+    println!("hola desde Rust en ReduxOS");
+    for (i, arg) in rt::args().enumerate() {
+        println!("  argv[{}] = {}", i, arg);
+    }
+
+    // The config registry keeps the counter across reboots.
+    let runs = match config::get(RUNS_KEY) {
+        Ok(value) => value.and_then(|v| v.parse::<u64>().ok()).unwrap_or(0) + 1,
+        Err(error) => {
+            println!("config no disponible: {}", error);
+            1
+        }
+    };
+    let _ = config::set(RUNS_KEY, &alloc::format!("{}", runs));
+    println!("ejecucion numero {}", runs);
+
+    let note = alloc::format!("rust_hello: ejecucion {} en t={} ms\n", runs, time::now_ms());
+    fs::write(NOTE_PATH, note.as_bytes())?;
+    println!("escrito {} ({} bytes)", NOTE_PATH, fs::metadata(NOTE_PATH)?.len);
+
+    let entries = fs::read_dir("/")?;
+    println!("{} entradas en /:", entries.len());
+    for entry in entries.iter().take(8) {
+        println!("  {}{}", entry.name, if entry.is_dir { "/" } else { "" });
+    }
+
+    let url = rt::args().nth(1).unwrap_or("http://example.com/");
+    match http::get(url) {
+        Ok(response) => {
+            let status = response.status;
+            let body = response.bytes()?;
+            println!("GET {} -> {} ({} bytes)", url, status, body.len());
+        }
+        Err(Error::Errno(code)) => println!("GET {}: sin servicio HTTP nativo (errno {})", url, code),
+        Err(error) => println!("GET {}: {}", url, error),
+    }
+    Ok(())
+}
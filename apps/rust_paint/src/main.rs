@@ -0,0 +1,104 @@
+//! Window example for redux-sdk: a small paint app on the X11 server.
+//!
+//! Drag with the left button to paint, pick a color from the top strip,
+//! `c` clears the canvas and `q` quits.
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use redux_sdk::gui::{self, Display, Event, Window};
+use redux_sdk::println;
+
+redux_sdk::main!(main);
+
+const WIDTH: u16 = 480;
+const HEIGHT: u16 = 320;
+const STRIP: u16 = 24;
+const BRUSH: u16 = 6;
+const BACKGROUND: u32 = 0xFFFFFF;
+
+/// Hue ramp for the color strip, `width` pixels wide and `STRIP` high.
+fn palette(width: u16) -> Vec<u32> {
+    let mut row = Vec::with_capacity(width as usize);
+    for x in 0..width as u32 {
+        let t = x * 6 * 255 / width.max(1) as u32;
+        let (sector, f) = (t / 255, t % 255);
+        let (r, g, b) = match sector {
+            0 => (255, f, 0),
+            1 => (255 - f, 255, 0),
+            2 => (0, 255, f),
+            3 => (0, 255 - f, 255),
+            4 => (f, 0, 255),
+            _ => (255, 0, 255 - f),
+        };
+        row.push((r << 16) | (g << 8) | b);
+    }
+    let mut pixels = Vec::with_capacity(row.len() * STRIP as usize);
+    for _ in 0..STRIP {
+        pixels.extend_from_slice(&row);
+    }
+    pixels
+}
+
+fn draw_chrome(display: &mut Display, window: &Window, strip: &[u32]) -> redux_sdk::Result<()> {
+    display.put_pixels(window, 0, 0, window.width, STRIP, strip)
+}
+
+fn main() -> redux_sdk::Result<()> {
+    let mut display = Display::connect()?;
+    let mut window = display.create_window("Rust Paint", WIDTH, HEIGHT, BACKGROUND)?;
+    let mut strip = palette(window.width);
+    let mut color = 0x000000;
+    let mut drawing = false;
+    println!("rust_paint: arrastra para pintar, c limpia, q sale");
+
+    loop {
+        let Some(event) = display.next_event(None)? else {
+            continue;
+        };
+        match event {
+            Event::Expose { .. } => draw_chrome(&mut display, &window, &strip)?,
+            Event::Resize { width, height, .. } => {
+                window.width = width;
+                window.height = height;
+                strip = palette(width);
+                display.clear(&window, BACKGROUND)?;
+                draw_chrome(&mut display, &window, &strip)?;
+            }
+            Event::Button {
+                button: 1,
+                x,
+                y,
+                pressed,
+            } => {
+                if pressed && y < STRIP as i16 {
+                    color = strip[x.clamp(0, window.width as i16 - 1) as usize];
+                    continue;
+                }
+                drawing = pressed;
+                if pressed {
+                    display.fill_rect(&window, x - BRUSH as i16 / 2, y - BRUSH as i16 / 2, BRUSH, BRUSH, color)?;
+                }
+            }
+            Event::Motion { x, y } if drawing && y >= STRIP as i16 => {
+                display.fill_rect(&window, x - BRUSH as i16 / 2, y - BRUSH as i16 / 2, BRUSH, BRUSH, color)?;
+            }
+            Event::Key { keycode, pressed: true } => match gui::key_char(keycode) {
+                Some('c') => {
+                    display.clear(&window, BACKGROUND)?;
+                    draw_chrome(&mut display, &window, &strip)?;
+                }
+                Some('q') => break,
+                _ => {}
+            },
+            Event::Closed { .. } => return Ok(()),
+            Event::Error { code, major } => println!("rust_paint: error X11 {} en opcode {}", code, major),
+            _ => {}
+        }
+    }
+    display.destroy_window(window)
+}
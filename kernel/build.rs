@@ -2,6 +2,7 @@ use std::env;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
 const RUNTIME_COPY_MAX_BYTES: usize = 256 * 1024 * 1024;
 const LINUXRT_BUNDLE_MAGIC: &[u8; 6] = b"RLTB1\0";
@@ -356,6 +357,31 @@ fn build_linuxrt_bundle(manifest_dir: &Path, out_dir: &Path) -> PathBuf {
     bundle_path
 }
 
+/// Short hash of the checked-out commit, with `-dirty` for local changes,
+/// so benchmark results can be told apart by build.
+fn git_revision(manifest_dir: &Path) -> String {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(manifest_dir)
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+            .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+    };
+    let Some(rev) = git(&["rev-parse", "--short=12", "HEAD"]) else {
+        return String::from("unknown");
+    };
+    if let Some(git_dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        println!("cargo:rerun-if-changed={}/HEAD", git_dir);
+        println!("cargo:rerun-if-changed={}/index", git_dir);
+    }
+    match git(&["status", "--porcelain", "--untracked-files=no"]) {
+        Some(changes) if !changes.is_empty() => format!("{}-dirty", rev),
+        _ => rev,
+    }
+}
+
 fn main() {
     println!("cargo:rustc-check-cfg=cfg(servo_external_unavailable)");
     println!("cargo:rustc-check-cfg=cfg(vaev_external_unavailable)");
@@ -369,6 +395,7 @@ fn main() {
     let target = env::var("TARGET").unwrap_or_default();
     let linuxrt_bundle = build_linuxrt_bundle(manifest_dir.as_path(), out_dir.as_path());
     println!("cargo:rustc-env=REDUX_LINUXRT_BUNDLE={}", linuxrt_bundle.display());
+    println!("cargo:rustc-env=REDUX_GIT_REV={}", git_revision(manifest_dir.as_path()));
 
     if env::var_os("CARGO_FEATURE_SERVO_EXTERNAL").is_none() {
     } else {
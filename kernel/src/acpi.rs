@@ -5,13 +5,18 @@ use core::ptr;
 use core::slice;
 use core::sync::atomic::{AtomicU8, Ordering};
 
+use crate::spinlock::SpinLock;
+
 const RSDP_SIGNATURE: [u8; 8] = *b"RSD PTR ";
 const FADT_SIGNATURE: u32 = u32::from_le_bytes(*b"FACP");
 const DSDT_SIGNATURE: u32 = u32::from_le_bytes(*b"DSDT");
 
 const ACPI_ADDRESS_SPACE_SYSTEM_MEMORY: u8 = 0;
 const ACPI_ADDRESS_SPACE_SYSTEM_IO: u8 = 1;
+const ACPI_ADDRESS_SPACE_EMBEDDED_CONTROLLER: u8 = 3;
 
+const PM1_STS_PWRBTN: u16 = 1 << 8;
+const PM1_STS_SLPBTN: u16 = 1 << 9;
 const PM1_STS_WAK: u16 = 1 << 15;
 const PM1_CNT_SCI_EN: u16 = 1;
 const PM1_CNT_SLP_TYP_SHIFT: u16 = 10;
@@ -161,13 +166,22 @@ s3_wake_trampoline_prot:
     or eax, 0x20
     mov cr4, eax
 
+    // EFER.LME, plus EFER.NXE when CPUID 0x80000001 EDX[20] reports NX:
+    // the page tables carry NX bits once `wx` has run.
+    mov eax, 0x80000001
+    cpuid
+    and edx, 0x100000
+    shr edx, 9
+    mov esi, edx
     mov ecx, 0xC0000080
     rdmsr
     or eax, 0x100
+    or eax, esi
     wrmsr
 
+    // PG and WP.
     mov eax, cr0
-    or eax, 0x80000000
+    or eax, 0x80010000
     mov cr0, eax
 
     push 0x08
@@ -195,6 +209,18 @@ s3_wake_trampoline_x64_entry:
     cld
     mov al, 0xB0
     out 0x80, al
+    mov eax, 0x80000001
+    cpuid
+    and edx, 0x100000
+    shr edx, 9
+    mov esi, edx
+    mov ecx, 0xC0000080
+    rdmsr
+    or eax, esi
+    wrmsr
+    mov rax, cr0
+    or rax, 0x10000
+    mov cr0, rax
     mov rax, qword ptr [S3_TRAMP_CR3_ABS]
     mov cr3, rax
     mov rsp, qword ptr [S3_TRAMP_STACK_ABS]
@@ -522,19 +548,46 @@ fn clear_facs_wake_vector(ctx: &AcpiS3Context) {
 }
 
 fn discover_s3_context() -> Result<AcpiS3Context, &'static str> {
-    let rsdp = find_rsdp().ok_or("RSDP not found")?;
+    find_rsdp().ok_or("RSDP not found")?;
+    let fadt_phys = find_table_by_signature(FADT_SIGNATURE).ok_or("FADT/FACP not found")?;
+    parse_fadt_for_s3(fadt_phys)
+}
+
+fn find_table_by_signature(signature: u32) -> Option<u64> {
+    let rsdp = find_rsdp()?;
     let revision = unsafe { ptr::read_unaligned(ptr::addr_of!((*rsdp).revision)) };
     let xsdt = unsafe { ptr::read_unaligned(ptr::addr_of!((*rsdp).xsdt_address)) };
     let rsdt = unsafe { ptr::read_unaligned(ptr::addr_of!((*rsdp).rsdt_address)) };
 
-    let fadt_phys = if revision >= 2 && xsdt != 0 {
-        find_table_in_xsdt(xsdt, FADT_SIGNATURE)
+    if revision >= 2 && xsdt != 0 {
+        find_table_in_xsdt(xsdt, signature)
     } else {
-        find_table_in_rsdt(rsdt as u64, FADT_SIGNATURE)
+        find_table_in_rsdt(rsdt as u64, signature)
     }
-    .ok_or("FADT/FACP not found")?;
+}
 
-    parse_fadt_for_s3(fadt_phys)
+/// Physical address of the checksummed ACPI table with this signature.
+pub fn find_table(signature: &[u8; 4]) -> Option<u64> {
+    find_table_by_signature(u32::from_le_bytes(*signature))
+}
+
+/// I/O port of the ACPI PM timer and whether it counts 32 bits (TMR_VAL_EXT)
+/// instead of 24. `None` when the FADT has no port-mapped timer.
+pub fn pm_timer() -> Option<(u16, bool)> {
+    let fadt_phys = find_table_by_signature(FADT_SIGNATURE)?;
+    if !table_valid(fadt_phys, FADT_SIGNATURE) {
+        return None;
+    }
+    let len = table_len(fadt_phys)?;
+    if read_u8_at(fadt_phys, len, 91).unwrap_or(0) < 4 {
+        return None;
+    }
+    let reg = choose_register(fadt_phys, len, 76, 208)?;
+    if reg.space != ACPI_ADDRESS_SPACE_SYSTEM_IO || reg.address > u16::MAX as u64 {
+        return None;
+    }
+    let flags = read_u32_at(fadt_phys, len, 112).unwrap_or(0);
+    Some((reg.address as u16, flags & (1 << 8) != 0))
 }
 
 fn find_rsdp() -> Option<*const AcpiRsdp> {
@@ -929,3 +982,377 @@ fn write_register_u16(reg: AcpiRegister, value: u16) {
         _ => {}
     }
 }
+
+/// PM1 event blocks and the lid switch, found once from the FADT and DSDT.
+#[derive(Clone, Copy)]
+struct PowerInputs {
+    pm1a_evt: Option<AcpiRegister>,
+    pm1b_evt: Option<AcpiRegister>,
+    lid: Option<LidSource>,
+}
+
+static POWER_INPUTS: SpinLock<Option<PowerInputs>> = SpinLock::new(None);
+
+fn power_inputs() -> PowerInputs {
+    let mut cached = POWER_INPUTS.lock();
+    if let Some(inputs) = *cached {
+        return inputs;
+    }
+    let inputs = discover_power_inputs();
+    *cached = Some(inputs);
+    inputs
+}
+
+fn discover_power_inputs() -> PowerInputs {
+    let mut inputs = PowerInputs {
+        pm1a_evt: None,
+        pm1b_evt: None,
+        lid: None,
+    };
+    if find_rsdp().is_none() {
+        return inputs;
+    }
+    let Some(fadt_phys) = find_table_by_signature(FADT_SIGNATURE) else {
+        return inputs;
+    };
+    if !table_valid(fadt_phys, FADT_SIGNATURE) {
+        return inputs;
+    }
+    let Some(len) = table_len(fadt_phys) else {
+        return inputs;
+    };
+    inputs.pm1a_evt = choose_register(fadt_phys, len, 56, 148);
+    inputs.pm1b_evt = choose_register(fadt_phys, len, 60, 160);
+    let dsdt_phys = choose_u64_field(fadt_phys, len, 40, 140);
+    if dsdt_phys != 0 && table_valid(dsdt_phys, DSDT_SIGNATURE) {
+        if let Some(dsdt_len) = table_len(dsdt_phys) {
+            let bytes = unsafe { slice::from_raw_parts(dsdt_phys as *const u8, dsdt_len) };
+            inputs.lid = find_lid_source(&bytes[36.min(bytes.len())..]);
+        }
+    }
+    inputs
+}
+
+/// Fixed-feature button presses latched since the last call, as
+/// (power button, sleep button). The status bits are write-one-to-clear.
+pub fn take_button_events() -> (bool, bool) {
+    let inputs = power_inputs();
+    let mut power = false;
+    let mut sleep = false;
+    for reg in [inputs.pm1a_evt, inputs.pm1b_evt].into_iter().flatten() {
+        let status = read_register_u16(reg);
+        let latched = status & (PM1_STS_PWRBTN | PM1_STS_SLPBTN);
+        if latched != 0 {
+            write_register_u16(reg, latched);
+        }
+        power |= status & PM1_STS_PWRBTN != 0;
+        sleep |= status & PM1_STS_SLPBTN != 0;
+    }
+    (power, sleep)
+}
+
+/// Whether the lid is open, read from the field the DSDT's `_LID` method
+/// returns. `None` when the machine has no lid or it lives somewhere the
+/// kernel cannot read without an AML interpreter.
+pub fn lid_open() -> Option<bool> {
+    let lid = power_inputs().lid?;
+    let byte = match lid.space {
+        ACPI_ADDRESS_SPACE_SYSTEM_MEMORY => unsafe { (lid.address as *const u8).read_volatile() },
+        ACPI_ADDRESS_SPACE_SYSTEM_IO => unsafe { crate::hal::inb(u16::try_from(lid.address).ok()?) },
+        ACPI_ADDRESS_SPACE_EMBEDDED_CONTROLLER => ec_read(u8::try_from(lid.address).ok()?)?,
+        _ => return None,
+    };
+    Some(((byte >> lid.bit) & 1 != 0) != lid.inverted)
+}
+
+pub fn lid_status_line() -> String {
+    match power_inputs().lid {
+        Some(lid) => format!(
+            "_LID: {}[{:#x}].{}{} -> {}",
+            match lid.space {
+                ACPI_ADDRESS_SPACE_EMBEDDED_CONTROLLER => "ec",
+                space => register_space_name(space),
+            },
+            lid.address,
+            lid.bit,
+            if lid.inverted { " (invertido)" } else { "" },
+            match lid_open() {
+                Some(true) => "abierta",
+                Some(false) => "cerrada",
+                None => "sin lectura",
+            }
+        ),
+        None => String::from("_LID: no encontrado en el DSDT"),
+    }
+}
+
+const EC_DATA_PORT: u16 = 0x62;
+const EC_COMMAND_PORT: u16 = 0x66;
+const EC_STATUS_OBF: u8 = 1 << 0;
+const EC_STATUS_IBF: u8 = 1 << 1;
+const EC_COMMAND_READ: u8 = 0x80;
+
+fn ec_wait(mask: u8, set: bool) -> Option<()> {
+    for _ in 0..100_000 {
+        let status = unsafe { crate::hal::inb(EC_COMMAND_PORT) };
+        if (status & mask != 0) == set {
+            return Some(());
+        }
+        crate::hal::pause();
+    }
+    None
+}
+
+fn ec_read(address: u8) -> Option<u8> {
+    ec_wait(EC_STATUS_IBF, false)?;
+    unsafe { crate::hal::outb(EC_COMMAND_PORT, EC_COMMAND_READ) };
+    ec_wait(EC_STATUS_IBF, false)?;
+    unsafe { crate::hal::outb(EC_DATA_PORT, address) };
+    ec_wait(EC_STATUS_OBF, true)?;
+    Some(unsafe { crate::hal::inb(EC_DATA_PORT) })
+}
+
+/// One bit of an operation region: where `_LID` gets its answer.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct LidSource {
+    space: u8,
+    address: u64,
+    bit: u8,
+    inverted: bool,
+}
+
+const AML_METHOD_OP: u8 = 0x14;
+const AML_RETURN_OP: u8 = 0xA4;
+const AML_IF_OP: u8 = 0xA0;
+const AML_LNOT_OP: u8 = 0x92;
+const AML_EXT_OP_PREFIX: u8 = 0x5B;
+const AML_OP_REGION_OP: u8 = 0x80;
+const AML_FIELD_OP: u8 = 0x81;
+
+/// Follows the usual `_LID` shapes, `Return (LIDS)` or `If (LIDS) {...}`,
+/// to the named field and its operation region. Anything cleverer needs
+/// a real AML interpreter and is reported as no lid.
+fn find_lid_source(aml: &[u8]) -> Option<LidSource> {
+    let body = find_method_body(aml, *b"_LID")?;
+    let (field, inverted) = lid_field_name(body)?;
+    let (region, bit_offset) = find_field_unit(aml, field)?;
+    let (space, offset) = find_op_region(aml, region)?;
+    Some(LidSource {
+        space,
+        address: offset + (bit_offset / 8) as u64,
+        bit: (bit_offset % 8) as u8,
+        inverted,
+    })
+}
+
+fn find_method_body(aml: &[u8], name: [u8; 4]) -> Option<&[u8]> {
+    let mut i = 0usize;
+    while i + 4 <= aml.len() {
+        if aml[i..i + 4] == name {
+            // MethodOp PkgLength NameString MethodFlags: the name is
+            // preceded by one to four length bytes.
+            for len_bytes in 1..=4usize {
+                let Some(op) = i.checked_sub(len_bytes + 1) else {
+                    break;
+                };
+                if aml[op] != AML_METHOD_OP {
+                    continue;
+                }
+                if let Some((pkg_len, used)) = parse_aml_pkg_length(aml, op + 1) {
+                    let end = op + 1 + pkg_len;
+                    if used == len_bytes && end <= aml.len() && i + 5 <= end {
+                        return Some(&aml[i + 5..end]);
+                    }
+                }
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+fn lid_field_name(body: &[u8]) -> Option<([u8; 4], bool)> {
+    let mut i = 0usize;
+    while i < body.len() {
+        let start = match body[i] {
+            AML_RETURN_OP => Some(i + 1),
+            AML_IF_OP => parse_aml_pkg_length(body, i + 1).map(|(_, used)| i + 1 + used),
+            _ => None,
+        };
+        if let Some(mut pos) = start {
+            let inverted = body.get(pos) == Some(&AML_LNOT_OP);
+            if inverted {
+                pos += 1;
+            }
+            if let Some((seg, _)) = parse_name_string(body, pos) {
+                return Some((seg, inverted));
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+fn is_name_seg(bytes: &[u8]) -> bool {
+    bytes.len() == 4
+        && (bytes[0].is_ascii_uppercase() || bytes[0] == b'_')
+        && bytes[1..]
+            .iter()
+            .all(|&b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_')
+}
+
+/// Last segment of the NameString at `offset` and the bytes it spans.
+fn parse_name_string(bytes: &[u8], offset: usize) -> Option<([u8; 4], usize)> {
+    let mut pos = offset;
+    while matches!(bytes.get(pos), Some(b'\\') | Some(b'^')) {
+        pos += 1;
+    }
+    let count = match *bytes.get(pos)? {
+        0x2E => {
+            pos += 1;
+            2
+        }
+        0x2F => {
+            pos += 2;
+            *bytes.get(pos - 1)? as usize
+        }
+        _ => 1,
+    };
+    if count == 0 {
+        return None;
+    }
+    let end = pos + 4 * count;
+    let last = bytes.get(end - 4..end)?;
+    if !(0..count).all(|seg| is_name_seg(&bytes[pos + 4 * seg..pos + 4 * seg + 4])) {
+        return None;
+    }
+    Some(([last[0], last[1], last[2], last[3]], end - offset))
+}
+
+/// Region name and bit offset of the field unit called `name`.
+fn find_field_unit(aml: &[u8], name: [u8; 4]) -> Option<([u8; 4], usize)> {
+    let mut i = 0usize;
+    while i + 2 < aml.len() {
+        if aml[i] == AML_EXT_OP_PREFIX && aml[i + 1] == AML_FIELD_OP {
+            if let Some(found) = scan_field_list(aml, i + 2, name) {
+                return Some(found);
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+fn scan_field_list(aml: &[u8], offset: usize, name: [u8; 4]) -> Option<([u8; 4], usize)> {
+    let (pkg_len, used) = parse_aml_pkg_length(aml, offset)?;
+    let end = (offset + pkg_len).min(aml.len());
+    let (region, name_len) = parse_name_string(aml, offset + used)?;
+    let mut pos = offset + used + name_len + 1; // FieldFlags
+    let mut bit = 0usize;
+    while pos < end {
+        match aml[pos] {
+            0x00 => {
+                let (bits, used) = parse_aml_pkg_length(aml, pos + 1)?;
+                bit += bits;
+                pos += 1 + used;
+            }
+            0x01 => pos += 3,
+            0x03 => pos += 4,
+            _ => {
+                let seg = aml.get(pos..pos + 4)?;
+                if !is_name_seg(seg) {
+                    return None;
+                }
+                let (bits, used) = parse_aml_pkg_length(aml, pos + 4)?;
+                if seg == name {
+                    return Some((region, bit));
+                }
+                bit += bits;
+                pos += 4 + used;
+            }
+        }
+    }
+    None
+}
+
+/// Address space and constant offset of the operation region `name`.
+fn find_op_region(aml: &[u8], name: [u8; 4]) -> Option<(u8, u64)> {
+    let mut i = 0usize;
+    while i + 2 < aml.len() {
+        if aml[i] == AML_EXT_OP_PREFIX && aml[i + 1] == AML_OP_REGION_OP {
+            if let Some((seg, used)) = parse_name_string(aml, i + 2) {
+                if seg == name {
+                    let space = *aml.get(i + 2 + used)?;
+                    let (offset, _) = parse_aml_integer(aml, i + 3 + used)?;
+                    return Some((space, offset));
+                }
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+crate::selftest::kernel_tests! {
+    "acpi";
+
+    fn lid_found_through_ec_field() {
+        let mut aml = alloc::vec::Vec::new();
+        // OperationRegion (ECOR, EmbeddedControl, 0x00, 0xFF)
+        aml.extend_from_slice(&[0x5B, 0x80]);
+        aml.extend_from_slice(b"ECOR");
+        aml.extend_from_slice(&[0x03, 0x00, 0x0A, 0xFF]);
+        // Field (ECOR, ByteAcc, Lock, Preserve) { Offset (0x46), , 3, LIDS, 1 }
+        aml.extend_from_slice(&[0x5B, 0x81, 0x10]);
+        aml.extend_from_slice(b"ECOR");
+        aml.extend_from_slice(&[0x11, 0x00, 0x40, 0x23]);
+        aml.extend_from_slice(&[0x00, 0x03]);
+        aml.extend_from_slice(b"LIDS");
+        aml.push(0x01);
+        // Method (_LID) { Return (^^EC0.LIDS) }
+        aml.extend_from_slice(&[0x14, 0x12]);
+        aml.extend_from_slice(b"_LID");
+        aml.extend_from_slice(&[0x00, 0xA4, b'^', b'^']);
+        aml.extend_from_slice(&[0x2E]);
+        aml.extend_from_slice(b"EC0_LIDS");
+        crate::selftest::ensure_eq(
+            find_lid_source(&aml),
+            Some(LidSource {
+                space: ACPI_ADDRESS_SPACE_EMBEDDED_CONTROLLER,
+                address: 0x46,
+                bit: 3,
+                inverted: false,
+            }),
+            "tapa en el EC",
+        )
+    }
+
+    fn lid_if_with_lnot_is_inverted() {
+        let mut aml = alloc::vec::Vec::new();
+        aml.extend_from_slice(&[0x5B, 0x80]);
+        aml.extend_from_slice(b"GPIO");
+        aml.extend_from_slice(&[0x01, 0x0B, 0x00, 0x05, 0x0A, 0x10]);
+        aml.extend_from_slice(&[0x5B, 0x81, 0x0B]);
+        aml.extend_from_slice(b"GPIO");
+        aml.extend_from_slice(&[0x01]);
+        aml.extend_from_slice(b"LCLS");
+        aml.push(0x01);
+        // Method (_LID) { If (LNot (LCLS)) { Return (One) } Return (Zero) }
+        aml.extend_from_slice(&[0x14, 0x11]);
+        aml.extend_from_slice(b"_LID");
+        aml.extend_from_slice(&[0x00, 0xA0, 0x08, 0x92]);
+        aml.extend_from_slice(b"LCLS");
+        aml.extend_from_slice(&[0xA4, 0x01, 0xA4, 0x00]);
+        crate::selftest::ensure_eq(
+            find_lid_source(&aml),
+            Some(LidSource {
+                space: ACPI_ADDRESS_SPACE_SYSTEM_IO,
+                address: 0x500,
+                bit: 0,
+                inverted: true,
+            }),
+            "tapa por GPIO",
+        )?;
+        crate::selftest::ensure_eq(find_lid_source(b"no aml here"), None, "sin _LID")
+    }
+}
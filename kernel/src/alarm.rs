@@ -0,0 +1,448 @@
+//! Alarms and countdown timers for the `alarm` command and the Reloj
+//! window.
+//!
+//! Alarms fire at a local time once, every day or on weekdays, and are kept
+//! in `\REDUXOS\ALARMS.TXT` (`07:30 diario on Despertar`). Countdown timers
+//! only live until reboot. Both ring through the notification center, as
+//! do calendar events that have a time. The compositor calls `service`
+//! from its background tasks.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::calendar::{self, hhmm, parse_hhmm, Date};
+use crate::gui::notifications::{self, Urgency};
+use crate::spinlock::SpinLock;
+
+const DIR: &str = "REDUXOS";
+const ALARMS_FILE: &str = "ALARMS.TXT";
+const MAX_ALARMS: usize = 32;
+const MAX_TIMERS: usize = 8;
+const LABEL_MAX_BYTES: usize = 40;
+const MAX_TIMER_MS: u64 = 24 * 3_600_000;
+const SOURCE: &str = "reloj";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Repeat {
+    Once,
+    Daily,
+    /// Monday to Friday.
+    Weekdays,
+}
+
+impl Repeat {
+    pub fn name(self) -> &'static str {
+        match self {
+            Repeat::Once => "una",
+            Repeat::Daily => "diario",
+            Repeat::Weekdays => "laborables",
+        }
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        match text {
+            "una" | "once" => Some(Repeat::Once),
+            "diario" | "daily" => Some(Repeat::Daily),
+            "laborables" | "weekdays" => Some(Repeat::Weekdays),
+            _ => None,
+        }
+    }
+
+    fn matches(self, date: Date) -> bool {
+        self != Repeat::Weekdays || date.weekday() < 5
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Alarm {
+    /// Minutes after local midnight.
+    pub minute: u16,
+    pub repeat: Repeat,
+    pub enabled: bool,
+    pub label: String,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Countdown {
+    pub deadline_ms: u64,
+    pub label: String,
+}
+
+struct State {
+    loaded: bool,
+    alarms: Vec<Alarm>,
+    timers: Vec<Countdown>,
+    /// Local minutes since the epoch at the last check, to ring once.
+    last_minute: i64,
+}
+
+static STATE: SpinLock<State> = SpinLock::new(State {
+    loaded: false,
+    alarms: Vec::new(),
+    timers: Vec::new(),
+    last_minute: 0,
+});
+
+fn trimmed_label(label: &str) -> String {
+    let mut label = String::from(label.trim());
+    while label.len() > LABEL_MAX_BYTES {
+        label.pop();
+    }
+    label
+}
+
+fn parse_alarms(text: &str) -> Vec<Alarm> {
+    let mut alarms = Vec::new();
+    for line in text.lines() {
+        let mut parts = line.trim().splitn(4, ' ');
+        let (Some(minute), Some(repeat), Some(enabled)) = (
+            parts.next().and_then(parse_hhmm),
+            parts.next().and_then(Repeat::parse),
+            parts.next(),
+        ) else {
+            continue;
+        };
+        alarms.push(Alarm {
+            minute,
+            repeat,
+            enabled: enabled == "on",
+            label: trimmed_label(parts.next().unwrap_or("")),
+        });
+        if alarms.len() >= MAX_ALARMS {
+            break;
+        }
+    }
+    alarms
+}
+
+fn serialize_alarms(alarms: &[Alarm]) -> String {
+    let mut out = String::new();
+    for alarm in alarms {
+        out.push_str(
+            alloc::format!(
+                "{} {} {} {}\n",
+                hhmm(alarm.minute),
+                alarm.repeat.name(),
+                if alarm.enabled { "on" } else { "off" },
+                alarm.label
+            )
+            .as_str(),
+        );
+    }
+    out
+}
+
+fn fs_ready() -> bool {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get() };
+    fat.init_status == crate::fat32::InitStatus::Success
+}
+
+fn ensure_loaded() {
+    if STATE.lock().loaded || !fs_ready() {
+        return;
+    }
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let alarms = fat
+        .ensure_subdirectory(fat.root_cluster, DIR)
+        .and_then(|dir| fat.read_file_in_dir(dir, ALARMS_FILE))
+        .map(|raw| parse_alarms(String::from_utf8_lossy(raw.as_slice()).as_ref()))
+        .unwrap_or_default();
+    {
+        let mut st = STATE.lock();
+        if st.loaded {
+            return;
+        }
+        st.loaded = true;
+        st.alarms = alarms;
+    }
+    // Timed events ring from the calendar cache.
+    calendar::ensure_loaded();
+}
+
+fn save(alarms: &[Alarm]) -> Result<(), &'static str> {
+    if !fs_ready() {
+        return Err("sin volumen FAT32 para guardar alarmas");
+    }
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let dir = fat.ensure_subdirectory(fat.root_cluster, DIR)?;
+    fat.write_text_file_in_dir(dir, ALARMS_FILE, serialize_alarms(alarms).as_bytes())
+}
+
+/// Apply `change` to the alarm list and write it back.
+fn update(change: impl FnOnce(&mut Vec<Alarm>) -> Result<(), &'static str>) -> Result<(), &'static str> {
+    ensure_loaded();
+    let alarms = {
+        let mut st = STATE.lock();
+        change(&mut st.alarms)?;
+        st.alarms.clone()
+    };
+    save(&alarms)
+}
+
+pub fn alarms() -> Vec<Alarm> {
+    ensure_loaded();
+    STATE.lock().alarms.clone()
+}
+
+pub fn add_alarm(minute: u16, repeat: Repeat, label: &str) -> Result<(), &'static str> {
+    update(|alarms| {
+        if alarms.len() >= MAX_ALARMS {
+            return Err("demasiadas alarmas");
+        }
+        alarms.push(Alarm {
+            minute,
+            repeat,
+            enabled: true,
+            label: trimmed_label(label),
+        });
+        alarms.sort_by_key(|a| a.minute);
+        Ok(())
+    })
+}
+
+pub fn remove_alarm(index: usize) -> Result<(), &'static str> {
+    update(|alarms| {
+        if index >= alarms.len() {
+            return Err("no hay esa alarma");
+        }
+        alarms.remove(index);
+        Ok(())
+    })
+}
+
+pub fn set_enabled(index: usize, enabled: bool) -> Result<(), &'static str> {
+    update(|alarms| match alarms.get_mut(index) {
+        Some(alarm) => {
+            alarm.enabled = enabled;
+            Ok(())
+        }
+        None => Err("no hay esa alarma"),
+    })
+}
+
+pub fn start_timer(duration_ms: u64, label: &str) -> Result<(), &'static str> {
+    if duration_ms == 0 || duration_ms > MAX_TIMER_MS {
+        return Err("duracion fuera de rango (1s..24h)");
+    }
+    let mut st = STATE.lock();
+    if st.timers.len() >= MAX_TIMERS {
+        return Err("demasiados temporizadores");
+    }
+    st.timers.push(Countdown {
+        deadline_ms: crate::timer::now_ms() + duration_ms,
+        label: trimmed_label(label),
+    });
+    st.timers.sort_by_key(|t| t.deadline_ms);
+    Ok(())
+}
+
+pub fn cancel_timer(index: usize) -> bool {
+    let mut st = STATE.lock();
+    if index >= st.timers.len() {
+        return false;
+    }
+    st.timers.remove(index);
+    true
+}
+
+/// Running timers as (milliseconds left, label), soonest first.
+pub fn timers() -> Vec<(u64, String)> {
+    let now = crate::timer::now_ms();
+    STATE
+        .lock()
+        .timers
+        .iter()
+        .map(|t| (t.deadline_ms.saturating_sub(now), t.label.clone()))
+        .collect()
+}
+
+/// `H:MM:SS` or `MM:SS` for a timer.
+pub fn remaining_text(ms: u64) -> String {
+    let s = ms.div_ceil(1000);
+    if s >= 3600 {
+        alloc::format!("{}:{:02}:{:02}", s / 3600, s / 60 % 60, s % 60)
+    } else {
+        alloc::format!("{:02}:{:02}", s / 60, s % 60)
+    }
+}
+
+/// `90s`, `5m`, `1h`, `1h30m` or plain minutes.
+pub fn parse_duration(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if let Ok(minutes) = text.parse::<u64>() {
+        return Some(minutes * 60_000);
+    }
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in text.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'h' | 'm' | 's' => {
+                let n = number.parse::<u64>().ok()?;
+                number.clear();
+                let unit = match c {
+                    'h' => 3_600_000,
+                    'm' => 60_000,
+                    _ => 1_000,
+                };
+                total = total.checked_add(n.checked_mul(unit)?)?;
+            }
+            _ => return None,
+        }
+    }
+    (number.is_empty() && total > 0).then_some(total)
+}
+
+/// Alarms that ring at `minute` of `date`.
+fn due_alarms(alarms: &[Alarm], date: Date, minute: u16) -> Vec<usize> {
+    (0..alarms.len())
+        .filter(|i| {
+            let alarm = &alarms[*i];
+            alarm.enabled && alarm.minute == minute && alarm.repeat.matches(date)
+        })
+        .collect()
+}
+
+/// Ring whatever is due: timers, alarms and timed calendar events.
+/// Returns true when something rang.
+pub fn service() -> bool {
+    ensure_loaded();
+    let now_ms = crate::timer::now_ms();
+    let (date, minute) = calendar::local_now();
+    let minute_index = date.days() * 1440 + minute as i64;
+
+    let mut ring: Vec<(String, String)> = Vec::new();
+    let mut changed_alarms = false;
+    {
+        let mut st = STATE.lock();
+        if st.last_minute == minute_index && st.timers.first().is_none_or(|t| t.deadline_ms > now_ms) {
+            return false;
+        }
+        while st.timers.first().is_some_and(|t| t.deadline_ms <= now_ms) {
+            let timer = st.timers.remove(0);
+            let label = if timer.label.is_empty() {
+                String::from("Tiempo cumplido")
+            } else {
+                timer.label
+            };
+            ring.push((String::from("Temporizador"), label));
+        }
+        // The first check after boot only records the minute, and a clock
+        // set backwards does not ring the same minute twice.
+        if st.last_minute != 0 && minute_index > st.last_minute && st.loaded {
+            for index in due_alarms(&st.alarms, date, minute) {
+                let alarm = &mut st.alarms[index];
+                ring.push((alloc::format!("Alarma {}", hhmm(alarm.minute)), alarm.label.clone()));
+                if alarm.repeat == Repeat::Once {
+                    alarm.enabled = false;
+                    changed_alarms = true;
+                }
+            }
+            for event in calendar::due_events(date, minute) {
+                ring.push((alloc::format!("Evento {}", hhmm(minute)), event.text));
+            }
+        }
+        st.last_minute = minute_index;
+    }
+
+    if changed_alarms {
+        let alarms = STATE.lock().alarms.clone();
+        let _ = save(&alarms);
+    }
+    for (title, body) in ring.iter() {
+        notifications::post(SOURCE, title.as_str(), body.as_str(), Urgency::Warning);
+    }
+    !ring.is_empty()
+}
+
+fn status_lines() -> Vec<String> {
+    let mut out = Vec::new();
+    let (date, minute) = calendar::local_now();
+    out.push(alloc::format!("Hora local: {} {}", date.to_text(), hhmm(minute)));
+    let alarms = alarms();
+    if alarms.is_empty() {
+        out.push(String::from("Sin alarmas."));
+    }
+    for (i, alarm) in alarms.iter().enumerate() {
+        out.push(alloc::format!(
+            "{:>3}. {} {:<10} {:<3} {}",
+            i + 1,
+            hhmm(alarm.minute),
+            alarm.repeat.name(),
+            if alarm.enabled { "on" } else { "off" },
+            alarm.label
+        ));
+    }
+    for (i, (left, label)) in timers().iter().enumerate() {
+        out.push(alloc::format!("  t{}. {} {}", i + 1, remaining_text(*left), label));
+    }
+    out
+}
+
+pub fn command_lines(args: &str) -> Vec<String> {
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let index = |text: &str| text.parse::<usize>().ok().filter(|n| *n >= 1).map(|n| n - 1);
+    let result = match words.as_slice() {
+        [] | ["list"] => return status_lines(),
+        ["add", time, rest @ ..] => match parse_hhmm(time) {
+            Some(minute) => {
+                let (repeat, label) = match rest.first().and_then(|w| Repeat::parse(w)) {
+                    Some(repeat) => (repeat, rest[1..].join(" ")),
+                    None => (Repeat::Once, rest.join(" ")),
+                };
+                add_alarm(minute, repeat, label.as_str())
+                    .map(|_| alloc::format!("alarma a las {} ({})", hhmm(minute), repeat.name()))
+            }
+            None => Err("hora invalida (HH:MM)"),
+        },
+        ["del", n] => match index(n) {
+            Some(i) => remove_alarm(i).map(|_| String::from("alarma borrada")),
+            None => Err(USAGE),
+        },
+        [state @ ("on" | "off"), n] => match index(n) {
+            Some(i) => set_enabled(i, *state == "on").map(|_| alloc::format!("alarma {} {}", i + 1, state)),
+            None => Err(USAGE),
+        },
+        ["timer", duration, rest @ ..] => match parse_duration(duration) {
+            Some(ms) => start_timer(ms, rest.join(" ").as_str())
+                .map(|_| alloc::format!("temporizador de {}", remaining_text(ms))),
+            None => Err("duracion invalida (ej. 90s, 5m, 1h30m)"),
+        },
+        ["cancel", n] => match n.strip_prefix('t').and_then(index) {
+            Some(i) if cancel_timer(i) => Ok(String::from("temporizador cancelado")),
+            _ => Err("no hay ese temporizador"),
+        },
+        _ => Err(USAGE),
+    };
+    match result {
+        Ok(line) => alloc::vec![alloc::format!("alarm: {}", line)],
+        Err(err) => alloc::vec![alloc::format!("alarm: {}", err)],
+    }
+}
+
+const USAGE: &str = "uso: alarm [list|add HH:MM [una|diario|laborables] [texto]|del <n>|on <n>|off <n>|timer <dur> [texto]|cancel t<n>]";
+
+crate::selftest::kernel_tests! {
+    "alarm";
+
+    fn alarms_parse_and_match() {
+        let alarms = parse_alarms("07:30 laborables on Trabajo\n09:00 diario off\nbasura\n25:00 diario on X\n");
+        crate::selftest::ensure_eq(alarms.len(), 2, "alarmas validas")?;
+        crate::selftest::ensure_eq(parse_alarms(serialize_alarms(&alarms).as_str()), alarms.clone(), "ida y vuelta")?;
+        let thursday = Date::new(2026, 10, 15).ok_or("fecha")?;
+        let saturday = Date::new(2026, 10, 17).ok_or("fecha")?;
+        crate::selftest::ensure_eq(due_alarms(&alarms, thursday, 450), alloc::vec![0], "jueves")?;
+        crate::selftest::ensure(due_alarms(&alarms, saturday, 450).is_empty(), "sabado")?;
+        crate::selftest::ensure(due_alarms(&alarms, thursday, 540).is_empty(), "desactivada")
+    }
+
+    fn durations() {
+        crate::selftest::ensure_eq(parse_duration("90s"), Some(90_000), "segundos")?;
+        crate::selftest::ensure_eq(parse_duration("1h30m"), Some(5_400_000), "horas y minutos")?;
+        crate::selftest::ensure_eq(parse_duration("5"), Some(300_000), "minutos")?;
+        crate::selftest::ensure_eq(parse_duration("5x"), None, "unidad")?;
+        crate::selftest::ensure_eq(parse_duration("10m5"), None, "resto")?;
+        crate::selftest::ensure_eq(remaining_text(61_500).as_str(), "01:02", "mm:ss")?;
+        crate::selftest::ensure_eq(remaining_text(3_600_000).as_str(), "1:00:00", "h:mm:ss")
+    }
+}
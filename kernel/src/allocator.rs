@@ -8,9 +8,14 @@ const HEAP_MIN_MIB: usize = 64;
 const HEAP_MAX_MIB: usize = 65536;
 const HEAP_STEP_MIB: usize = 64;
 
-#[global_allocator]
+// `heapcheck` builds put `crate::heapcheck::Checked` in front of it.
+#[cfg_attr(not(feature = "heapcheck"), global_allocator)]
 static ALLOCATOR: LockedHeap = LockedHeap::empty();
+#[cfg(feature = "heapcheck")]
+#[global_allocator]
+static CHECKED: crate::heapcheck::Checked = crate::heapcheck::Checked;
 static HEAP_SIZE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static HEAP_START: AtomicUsize = AtomicUsize::new(0);
 static HEAP_RESERVED_BYTES: AtomicUsize = AtomicUsize::new(0);
 
 pub struct HeapReservation {
@@ -54,6 +59,30 @@ pub fn heap_size_bytes() -> usize {
     HEAP_SIZE_BYTES.load(Ordering::Relaxed)
 }
 
+/// Start address and size of the heap region.
+pub fn heap_range() -> (usize, usize) {
+    (HEAP_START.load(Ordering::Relaxed), heap_size_bytes())
+}
+
+/// Allocate from the heap itself, below `heapcheck`.
+pub(crate) unsafe fn raw_alloc(layout: core::alloc::Layout) -> *mut u8 {
+    unsafe { core::alloc::GlobalAlloc::alloc(&ALLOCATOR, layout) }
+}
+
+pub(crate) unsafe fn raw_dealloc(ptr: *mut u8, layout: core::alloc::Layout) {
+    unsafe { core::alloc::GlobalAlloc::dealloc(&ALLOCATOR, ptr, layout) }
+}
+
+/// Whether someone is inside the allocator right now; a fault handler must
+/// not allocate if so.
+pub fn heap_locked() -> bool {
+    ALLOCATOR.is_locked()
+}
+
+pub fn heap_used_bytes() -> usize {
+    ALLOCATOR.lock().used()
+}
+
 pub fn heap_reserved_bytes() -> usize {
     HEAP_RESERVED_BYTES.load(Ordering::Relaxed)
 }
@@ -151,6 +180,7 @@ pub fn init_heap() {
     unsafe {
         ALLOCATOR.lock().init(heap_ptr as *mut u8, heap_size);
     }
+    HEAP_START.store(heap_ptr, Ordering::Relaxed);
     HEAP_SIZE_BYTES.store(heap_size, Ordering::Relaxed);
     HEAP_RESERVED_BYTES.store(0, Ordering::Relaxed);
 }
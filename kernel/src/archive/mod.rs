@@ -0,0 +1,555 @@
+//! tar and zip archives: in-memory readers and writers, extraction into a
+//! FAT directory and archive creation from one.
+//!
+//! `Archive::open` takes the raw file (a `.tar.gz` is gunzipped first) and
+//! lists its members; `extract_to_dir` recreates the tree under a directory
+//! cluster, refusing absolute paths and `..`. The package installer uses the
+//! format parsers directly; the `tar`, `unzip` and `zip` commands go through
+//! `command_lines`.
+
+pub mod tar;
+pub mod zip;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::fs::{DirEntry, FileType};
+
+/// Largest archive, and largest single member, handled in memory.
+pub const ARCHIVE_MAX_BYTES: usize = 64 * 1024 * 1024;
+const ARCHIVE_MAX_DEPTH: usize = 16;
+const ARCHIVE_MAX_REPORTED_ERRORS: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ArchiveFormat::Tar => "tar",
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::Zip => "zip",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EntryKind {
+    File,
+    Directory,
+    /// Links, devices and FIFOs; listed but never extracted.
+    Other,
+}
+
+/// One archive member.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub path: String,
+    pub kind: EntryKind,
+    /// Uncompressed size.
+    pub size: usize,
+    /// Unix seconds.
+    pub mtime: u32,
+    /// tar: offset of the data. zip: offset of the local header.
+    pub offset: usize,
+    pub stored_size: usize,
+    /// zip compression method (`u16::MAX` for encrypted members); 0 for tar.
+    pub method: u16,
+    pub crc32: Option<u32>,
+}
+
+pub struct Archive {
+    pub format: ArchiveFormat,
+    pub entries: Vec<ArchiveEntry>,
+    raw: Vec<u8>,
+}
+
+impl Archive {
+    /// Detect the format from the contents and list the members.
+    pub fn open(raw: Vec<u8>) -> Result<Self, &'static str> {
+        if zip::is_zip(raw.as_slice()) {
+            let (entries, _) = zip::read_central_directory(raw.as_slice())?;
+            return Ok(Self {
+                format: ArchiveFormat::Zip,
+                entries,
+                raw,
+            });
+        }
+        let (format, raw) = if crate::compress::is_gzip(raw.as_slice()) {
+            let (_, tar) = crate::compress::gzip_decompress(raw.as_slice(), ARCHIVE_MAX_BYTES)?;
+            (ArchiveFormat::TarGz, tar)
+        } else {
+            (ArchiveFormat::Tar, raw)
+        };
+        if !tar::is_tar(raw.as_slice()) {
+            return Err("formato de archivo no reconocido (se espera tar, tar.gz o zip).");
+        }
+        let entries = tar::read_entries(raw.as_slice())?;
+        Ok(Self { format, entries, raw })
+    }
+
+    /// Contents of a file member.
+    pub fn read(&self, entry: &ArchiveEntry) -> Result<Vec<u8>, &'static str> {
+        if entry.kind != EntryKind::File {
+            return Err("la entrada no es un archivo.");
+        }
+        match self.format {
+            ArchiveFormat::Zip => zip::read_entry(self.raw.as_slice(), entry, ARCHIVE_MAX_BYTES),
+            ArchiveFormat::Tar | ArchiveFormat::TarGz => self
+                .raw
+                .get(entry.offset..entry.offset + entry.size)
+                .map(|data| data.to_vec())
+                .ok_or("tar truncado."),
+        }
+    }
+
+    /// First file member whose path matches `path` (ignoring case and a
+    /// leading `./`).
+    pub fn find(&self, path: &str) -> Option<&ArchiveEntry> {
+        let want = path.trim_start_matches("./").trim_start_matches('/');
+        self.entries
+            .iter()
+            .find(|e| e.kind == EntryKind::File && e.path.trim_start_matches("./").eq_ignore_ascii_case(want))
+    }
+}
+
+/// Path components of a member, or `None` if it would land outside the
+/// target directory (`..`, drive letters). Leading `/` and `./` are
+/// dropped, as GNU tar does.
+pub fn safe_components(path: &str) -> Option<Vec<&str>> {
+    let mut out = Vec::new();
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => continue,
+            ".." => return None,
+            _ if part.contains(':') => return None,
+            _ => out.push(part),
+        }
+    }
+    if out.is_empty() {
+        None
+    } else {
+        Some(out)
+    }
+}
+
+/// macOS resource forks and Finder metadata that zip tools leave behind.
+fn is_junk(components: &[&str]) -> bool {
+    components.first().is_some_and(|c| c.eq_ignore_ascii_case("__MACOSX"))
+        || components.last().is_some_and(|c| c.starts_with("._") || c.eq_ignore_ascii_case(".DS_Store"))
+}
+
+#[derive(Default)]
+pub struct ExtractReport {
+    pub files: usize,
+    pub dirs: usize,
+    pub bytes: usize,
+    pub skipped: usize,
+    pub errors: Vec<String>,
+}
+
+/// Directory cluster for `components` under `base`, creating what is missing.
+/// `cache` remembers directories already made during one extraction.
+fn ensure_dirs(base: u32, components: &[&str], cache: &mut Vec<(String, u32)>) -> Result<u32, &'static str> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let mut cluster = base;
+    let mut key = String::new();
+    for part in components {
+        key.push('/');
+        key.push_str(part.to_ascii_lowercase().as_str());
+        if let Some((_, cached)) = cache.iter().find(|(k, _)| *k == key) {
+            cluster = *cached;
+            continue;
+        }
+        cluster = fat.ensure_subdirectory(cluster, part)?;
+        cache.push((key.clone(), cluster));
+    }
+    Ok(cluster)
+}
+
+/// Recreate the archive tree under `dir_cluster`. `progress` is called with
+/// each member path before it is written.
+pub fn extract_to_dir(archive: &Archive, dir_cluster: u32, progress: &mut impl FnMut(&str)) -> ExtractReport {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let mut report = ExtractReport::default();
+    let mut cache = Vec::new();
+    for entry in archive.entries.iter() {
+        let Some(components) = safe_components(entry.path.as_str()) else {
+            report.skipped += 1;
+            report.errors.push(alloc::format!("{}: ruta insegura, omitida", entry.path));
+            continue;
+        };
+        if entry.kind == EntryKind::Other || is_junk(components.as_slice()) {
+            report.skipped += 1;
+            continue;
+        }
+        progress(entry.path.as_str());
+        if entry.kind == EntryKind::Directory {
+            match ensure_dirs(dir_cluster, components.as_slice(), &mut cache) {
+                Ok(_) => report.dirs += 1,
+                Err(e) => report.errors.push(alloc::format!("{}: {}", entry.path, e)),
+            }
+            continue;
+        }
+        let (leaf, parents) = components.split_last().unwrap_or((&"", &[]));
+        let result = ensure_dirs(dir_cluster, parents, &mut cache).and_then(|dir| {
+            let data = archive.read(entry)?;
+            fat.write_text_file_in_dir(dir, leaf, data.as_slice())?;
+            Ok(data.len())
+        });
+        match result {
+            Ok(len) => {
+                report.files += 1;
+                report.bytes += len;
+            }
+            Err(e) => report.errors.push(alloc::format!("{}: {}", entry.path, e)),
+        }
+    }
+    report
+}
+
+enum ArchiveSink {
+    Tar(tar::TarWriter),
+    Zip(zip::ZipWriter),
+}
+
+impl ArchiveSink {
+    fn add_file(&mut self, path: &str, data: &[u8], mtime: u32) -> Result<(), &'static str> {
+        match self {
+            ArchiveSink::Tar(w) => {
+                w.add_file(path, data, mtime);
+                Ok(())
+            }
+            ArchiveSink::Zip(w) => w.add_file(path, data, mtime),
+        }
+    }
+
+    fn add_dir(&mut self, path: &str, mtime: u32) -> Result<(), &'static str> {
+        match self {
+            ArchiveSink::Tar(w) => {
+                w.add_dir(path, mtime);
+                Ok(())
+            }
+            ArchiveSink::Zip(w) => w.add_dir(path, mtime),
+        }
+    }
+}
+
+fn entry_mtime(entry: &DirEntry) -> u32 {
+    zip::dos_to_unix(entry.write_time, entry.write_date)
+}
+
+/// Directory entry for `path` (relative, `/`-separated) under `dir_cluster`.
+fn lookup(dir_cluster: u32, path: &str) -> Result<DirEntry, String> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let components = safe_components(path).ok_or_else(|| alloc::format!("{}: ruta invalida", path))?;
+    let mut dir = dir_cluster;
+    let mut found = None;
+    for (i, part) in components.iter().enumerate() {
+        let entries = fat.read_dir_entries(dir).map_err(String::from)?;
+        let entry = entries
+            .into_iter()
+            .find(|e| e.valid && e.matches_name(part))
+            .ok_or_else(|| alloc::format!("{}: no encontrado", path))?;
+        if i + 1 < components.len() {
+            if entry.file_type != FileType::Directory {
+                return Err(alloc::format!("{}: no es una carpeta", part));
+            }
+            dir = if entry.cluster == 0 { fat.root_cluster } else { entry.cluster };
+        }
+        found = Some(entry);
+    }
+    found.ok_or_else(|| alloc::format!("{}: no encontrado", path))
+}
+
+fn add_tree(
+    sink: &mut ArchiveSink,
+    entry: &DirEntry,
+    path: &str,
+    depth: usize,
+    total: &mut usize,
+    files: &mut usize,
+) -> Result<(), String> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    if entry.file_type == FileType::File {
+        *total += entry.size as usize;
+        if *total > ARCHIVE_MAX_BYTES {
+            return Err(alloc::format!("el contenido supera {} bytes", ARCHIVE_MAX_BYTES));
+        }
+        let mut data = alloc::vec![0u8; entry.size as usize];
+        let len = fat.read_file_sized(entry.cluster, entry.size as usize, &mut data).map_err(String::from)?;
+        data.truncate(len);
+        sink.add_file(path, data.as_slice(), entry_mtime(entry)).map_err(String::from)?;
+        *files += 1;
+        return Ok(());
+    }
+    if depth >= ARCHIVE_MAX_DEPTH {
+        return Err(alloc::format!("{}: demasiados niveles de carpetas", path));
+    }
+    sink.add_dir(path, entry_mtime(entry)).map_err(String::from)?;
+    let cluster = if entry.cluster == 0 { fat.root_cluster } else { entry.cluster };
+    let children = fat.read_dir_entries(cluster).map_err(String::from)?;
+    for child in children.iter() {
+        let name = child.full_name();
+        if !child.valid || name == "." || name == ".." {
+            continue;
+        }
+        let child_path = alloc::format!("{}/{}", path, name);
+        add_tree(sink, child, child_path.as_str(), depth + 1, total, files)?;
+    }
+    Ok(())
+}
+
+/// Build an archive from files and folders under `dir_cluster`. Returns the
+/// archive bytes and the number of files stored.
+pub fn create(format: ArchiveFormat, dir_cluster: u32, paths: &[&str]) -> Result<(Vec<u8>, usize), String> {
+    let mut sink = match format {
+        ArchiveFormat::Zip => ArchiveSink::Zip(zip::ZipWriter::new(crate::compress::DEFAULT_LEVEL)),
+        ArchiveFormat::Tar | ArchiveFormat::TarGz => ArchiveSink::Tar(tar::TarWriter::new()),
+    };
+    let mut total = 0usize;
+    let mut files = 0usize;
+    for path in paths {
+        let entry = lookup(dir_cluster, path)?;
+        let name = safe_components(path).unwrap_or_default().join("/");
+        add_tree(&mut sink, &entry, name.as_str(), 0, &mut total, &mut files)?;
+    }
+    let raw = match sink {
+        ArchiveSink::Tar(w) => {
+            let tar = w.finish();
+            if format == ArchiveFormat::TarGz {
+                let mtime = (crate::timer::wall_clock_unix_millis() / 1000).max(0) as u32;
+                crate::compress::gzip_compress(tar.as_slice(), crate::compress::DEFAULT_LEVEL, None, mtime)
+            } else {
+                tar
+            }
+        }
+        ArchiveSink::Zip(w) => w.finish().map_err(String::from)?,
+    };
+    Ok((raw, files))
+}
+
+fn read_archive_file(dir_cluster: u32, name: &str) -> Result<Archive, String> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let raw = fat.read_file_in_dir(dir_cluster, name).map_err(|e| alloc::format!("{}: {}", name, e))?;
+    if raw.len() > ARCHIVE_MAX_BYTES {
+        return Err(alloc::format!("{}: demasiado grande (max {} bytes)", name, ARCHIVE_MAX_BYTES));
+    }
+    Archive::open(raw).map_err(|e| alloc::format!("{}: {}", name, e))
+}
+
+fn list_lines(archive: &Archive, out: &mut Vec<String>) {
+    let mut total = 0usize;
+    for entry in archive.entries.iter() {
+        let marker = match entry.kind {
+            EntryKind::File => ' ',
+            EntryKind::Directory => 'd',
+            EntryKind::Other => 'l',
+        };
+        out.push(alloc::format!("{} {:>10}  {}", marker, entry.size, entry.path));
+        total += entry.size;
+    }
+    out.push(alloc::format!(
+        "{} entradas, {} bytes ({})",
+        archive.entries.len(),
+        total,
+        archive.format.as_str()
+    ));
+}
+
+fn extract_lines(archive: &Archive, dir_cluster: u32, dest: Option<&str>, verbose: bool, out: &mut Vec<String>) {
+    let target = match dest {
+        Some(dest) => match safe_components(dest) {
+            Some(parts) => match ensure_dirs(dir_cluster, parts.as_slice(), &mut Vec::new()) {
+                Ok(cluster) => cluster,
+                Err(e) => {
+                    out.push(alloc::format!("{}: {}", dest, e));
+                    return;
+                }
+            },
+            None => {
+                out.push(alloc::format!("{}: destino invalido", dest));
+                return;
+            }
+        },
+        None => dir_cluster,
+    };
+    let mut names = Vec::new();
+    let report = extract_to_dir(archive, target, &mut |path| {
+        if verbose {
+            names.push(String::from(path));
+        }
+    });
+    out.extend(names);
+    out.push(alloc::format!(
+        "{} archivos y {} carpetas extraidos en {} ({} bytes), {} omitidos, {} errores.",
+        report.files,
+        report.dirs,
+        dest.unwrap_or("."),
+        report.bytes,
+        report.skipped,
+        report.errors.len()
+    ));
+    for error in report.errors.iter().take(ARCHIVE_MAX_REPORTED_ERRORS) {
+        out.push(alloc::format!("  {}", error));
+    }
+}
+
+fn create_lines(format: ArchiveFormat, dir_cluster: u32, name: &str, paths: &[&str], out: &mut Vec<String>) {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let result = create(format, dir_cluster, paths).and_then(|(raw, files)| {
+        fat.write_text_file_in_dir(dir_cluster, name, raw.as_slice())
+            .map(|_| (raw.len(), files))
+            .map_err(String::from)
+    });
+    match result {
+        Ok((len, files)) => out.push(alloc::format!("{}: {} archivos, {} bytes ({})", name, files, len, format.as_str())),
+        Err(e) => out.push(alloc::format!("{}: {}", name, e)),
+    }
+}
+
+/// Shared implementation of `tar`, `unzip` and `zip` on files of the FAT
+/// directory `dir_cluster`.
+pub fn command_lines(verb: &str, args: &str, dir_cluster: u32) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut words: Vec<&str> = args.split_whitespace().collect();
+
+    match verb {
+        "tar" => {
+            let usage = "Uso: tar xf|tf <archivo.tar[.gz]> [destino] | tar cf|czf <archivo> <ruta>...";
+            if words.len() < 2 {
+                out.push(String::from(usage));
+                return out;
+            }
+            let mode = words.remove(0).trim_start_matches('-');
+            let verbose = mode.contains('v');
+            let archive = words.remove(0);
+            if mode.contains('c') {
+                if words.is_empty() {
+                    out.push(String::from(usage));
+                    return out;
+                }
+                let lower = archive.to_ascii_lowercase();
+                let gz = mode.contains('z') || lower.ends_with(".tgz") || lower.ends_with(".gz");
+                let format = if gz { ArchiveFormat::TarGz } else { ArchiveFormat::Tar };
+                create_lines(format, dir_cluster, archive, words.as_slice(), &mut out);
+            } else if mode.contains('x') || mode.contains('t') {
+                match read_archive_file(dir_cluster, archive) {
+                    Ok(a) if a.format == ArchiveFormat::Zip => {
+                        out.push(alloc::format!("{}: es un zip (usa: unzip {})", archive, archive));
+                    }
+                    Ok(a) if mode.contains('t') => list_lines(&a, &mut out),
+                    Ok(a) => extract_lines(&a, dir_cluster, words.first().copied(), verbose, &mut out),
+                    Err(e) => out.push(alloc::format!("tar: {}", e)),
+                }
+            } else {
+                out.push(String::from(usage));
+            }
+        }
+        "unzip" => {
+            let list = words.first() == Some(&"-l");
+            if list {
+                words.remove(0);
+            }
+            let Some(archive) = words.first().copied() else {
+                out.push(String::from("Uso: unzip [-l] <archivo.zip> [destino]"));
+                return out;
+            };
+            match read_archive_file(dir_cluster, archive) {
+                Ok(a) if a.format != ArchiveFormat::Zip => {
+                    out.push(alloc::format!("{}: no es un zip (usa: tar xf {})", archive, archive));
+                }
+                Ok(a) if list => list_lines(&a, &mut out),
+                Ok(a) => extract_lines(&a, dir_cluster, words.get(1).copied(), false, &mut out),
+                Err(e) => out.push(alloc::format!("unzip: {}", e)),
+            }
+        }
+        _ => {
+            if words.len() < 2 {
+                out.push(String::from("Uso: zip <archivo.zip> <ruta>..."));
+                return out;
+            }
+            let archive = words.remove(0);
+            create_lines(ArchiveFormat::Zip, dir_cluster, archive, words.as_slice(), &mut out);
+        }
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "archive";
+
+    fn tar_roundtrip() {
+        let long = "carpeta/con/un/nombre/muy/largo/que/no/cabe/en/los/cien/bytes/del/campo/name/de/ustar/archivo.txt";
+        let deep = alloc::format!("{}/{}", "x".repeat(160), "y".repeat(120));
+        let mut w = tar::TarWriter::new();
+        w.add_dir("carpeta", 0);
+        w.add_file("carpeta/hola.txt", b"hola\n", 1_700_000_000);
+        w.add_file(long, b"largo", 0);
+        w.add_file(deep.as_str(), &[7u8; 1000], 0);
+        let raw = w.finish();
+        crate::selftest::ensure_eq(raw.len() % tar::TAR_BLOCK, 0, "bloques")?;
+        let archive = Archive::open(raw)?;
+        crate::selftest::ensure_eq(archive.format, ArchiveFormat::Tar, "formato")?;
+        crate::selftest::ensure_eq(archive.entries.len(), 4, "entradas")?;
+        crate::selftest::ensure_eq(archive.entries[0].kind, EntryKind::Directory, "carpeta")?;
+        crate::selftest::ensure_eq(archive.entries[1].mtime, 1_700_000_000, "mtime")?;
+        crate::selftest::ensure_eq(archive.entries[2].path.as_str(), long, "prefijo ustar")?;
+        crate::selftest::ensure_eq(archive.entries[3].path.as_str(), deep.as_str(), "nombre GNU largo")?;
+        let data = archive.read(archive.find("./carpeta/HOLA.TXT").ok_or("find")?)?;
+        crate::selftest::ensure(data == b"hola\n", "contenido")
+    }
+
+    fn tar_gz_detected() {
+        let mut w = tar::TarWriter::new();
+        w.add_file("a.txt", b"abc", 0);
+        let gz = crate::compress::gzip_compress(w.finish().as_slice(), 6, None, 0);
+        let archive = Archive::open(gz)?;
+        crate::selftest::ensure_eq(archive.format, ArchiveFormat::TarGz, "formato")?;
+        crate::selftest::ensure(archive.read(&archive.entries[0])? == b"abc", "contenido")
+    }
+
+    fn zip_roundtrip() {
+        let text = "linea repetida\n".repeat(200);
+        let mut w = zip::ZipWriter::new(6);
+        w.add_dir("docs", 1_700_000_000)?;
+        w.add_file("docs/texto.txt", text.as_bytes(), 1_700_000_000)?;
+        w.add_file("raw.bin", &[1, 2, 3], 0)?;
+        let archive = Archive::open(w.finish()?)?;
+        crate::selftest::ensure_eq(archive.format, ArchiveFormat::Zip, "formato")?;
+        crate::selftest::ensure_eq(archive.entries.len(), 3, "entradas")?;
+        let texto = &archive.entries[1];
+        crate::selftest::ensure_eq(texto.method, zip::ZIP_METHOD_DEFLATE, "deflate")?;
+        crate::selftest::ensure_eq(texto.mtime, 1_700_000_000, "fecha DOS")?;
+        crate::selftest::ensure(archive.read(texto)? == text.as_bytes(), "contenido deflate")?;
+        crate::selftest::ensure_eq(archive.entries[2].method, zip::ZIP_METHOD_STORE, "store")?;
+        crate::selftest::ensure(archive.read(&archive.entries[2])? == [1, 2, 3], "contenido store")
+    }
+
+    fn zip_detects_corruption() {
+        let mut w = zip::ZipWriter::new(0);
+        w.add_file("a.txt", b"contenido", 0)?;
+        let mut raw = w.finish()?;
+        raw[30 + 5] ^= 0xFF;
+        let archive = Archive::open(raw)?;
+        crate::selftest::ensure(archive.read(&archive.entries[0]).is_err(), "crc")
+    }
+
+    fn unsafe_paths_rejected() {
+        crate::selftest::ensure(safe_components("../etc/passwd").is_none(), "..")?;
+        crate::selftest::ensure(safe_components("a/../../b").is_none(), "a/../..")?;
+        crate::selftest::ensure(safe_components("C:/boot").is_none(), "unidad")?;
+        crate::selftest::ensure_eq(safe_components("/abs/./x"), Some(alloc::vec!["abs", "x"]), "absoluta")?;
+        crate::selftest::ensure(is_junk(&["__MACOSX", "a"]) && is_junk(&["d", "._a"]), "basura macOS")
+    }
+
+    fn tar_octal_fields() {
+        crate::selftest::ensure_eq(tar::parse_octal(b"0000644\0"), Some(0o644), "octal")?;
+        crate::selftest::ensure_eq(tar::parse_octal(b"  755 \0"), Some(0o755), "espacios")?;
+        crate::selftest::ensure_eq(tar::parse_octal(&[0x80, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0]), Some(0x20000), "base 256")
+    }
+}
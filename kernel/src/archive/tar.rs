@@ -0,0 +1,268 @@
+//! POSIX ustar reader and writer, with the GNU `L` long-name and pax `x`
+//! extensions on the read side.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::{ArchiveEntry, EntryKind};
+
+pub const TAR_BLOCK: usize = 512;
+
+const TAR_NAME_LEN: usize = 100;
+const TAR_PREFIX_LEN: usize = 155;
+const TAR_GNU_LONGLINK: &str = "././@LongLink";
+
+fn field_str(field: &[u8]) -> &[u8] {
+    let len = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    &field[..len]
+}
+
+/// Numeric header field: octal digits padded with spaces or NULs, or the
+/// GNU base-256 form (high bit of the first byte set) used for sizes over
+/// 8 GiB. Empty fields read as 0, like GNU tar.
+pub fn parse_octal(field: &[u8]) -> Option<u64> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        let mut value = (field[0] & 0x7F) as u64;
+        for &b in &field[1..] {
+            value = value.checked_mul(256)?.checked_add(b as u64)?;
+        }
+        return Some(value);
+    }
+    let mut value = 0u64;
+    let mut saw_digit = false;
+    for &b in field {
+        if (b'0'..=b'7').contains(&b) {
+            saw_digit = true;
+            value = value.saturating_mul(8).saturating_add((b - b'0') as u64);
+        } else if saw_digit {
+            break;
+        }
+    }
+    Some(value)
+}
+
+fn checksum(block: &[u8]) -> u64 {
+    block
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| if (148..156).contains(&i) { b' ' as u64 } else { b as u64 })
+        .sum()
+}
+
+fn header_ok(block: &[u8]) -> bool {
+    block.len() >= TAR_BLOCK && parse_octal(&block[148..156]) == Some(checksum(&block[..TAR_BLOCK]))
+}
+
+/// Whether `raw` starts with a valid tar header.
+pub fn is_tar(raw: &[u8]) -> bool {
+    header_ok(raw) && raw[..TAR_BLOCK].iter().any(|&b| b != 0)
+}
+
+fn padded(size: usize) -> usize {
+    size.div_ceil(TAR_BLOCK) * TAR_BLOCK
+}
+
+/// `path` and `size` records of a pax extended header ("<len> key=value\n").
+fn parse_pax(data: &[u8]) -> (Option<String>, Option<usize>) {
+    let mut path = None;
+    let mut size = None;
+    let mut rest = data;
+    while !rest.is_empty() {
+        let Some(space) = rest.iter().position(|&b| b == b' ') else {
+            break;
+        };
+        let Some(len) = core::str::from_utf8(&rest[..space]).ok().and_then(|l| l.parse::<usize>().ok()) else {
+            break;
+        };
+        if len <= space + 1 || len > rest.len() {
+            break;
+        }
+        let record = &rest[space + 1..len];
+        let record = record.strip_suffix(b"\n").unwrap_or(record);
+        if let Some(eq) = record.iter().position(|&b| b == b'=') {
+            let value = String::from_utf8_lossy(&record[eq + 1..]).into_owned();
+            match &record[..eq] {
+                b"path" => path = Some(value),
+                b"size" => size = value.parse().ok(),
+                _ => {}
+            }
+        }
+        rest = &rest[len..];
+    }
+    (path, size)
+}
+
+/// List the members of a tar archive. Data stays in `raw`; each entry keeps
+/// its offset there.
+pub fn read_entries(raw: &[u8]) -> Result<Vec<ArchiveEntry>, &'static str> {
+    let mut entries = Vec::new();
+    let mut pos = 0usize;
+    let mut long_name: Option<String> = None;
+    let mut pax_size: Option<usize> = None;
+
+    while pos + TAR_BLOCK <= raw.len() {
+        let block = &raw[pos..pos + TAR_BLOCK];
+        if block.iter().all(|&b| b == 0) {
+            break;
+        }
+        if !header_ok(block) {
+            return Err("tar: cabecera corrupta (checksum).");
+        }
+        let typeflag = block[156];
+        let header_size = parse_octal(&block[124..136]).ok_or("tar: tamano invalido.")? as usize;
+        let size = match typeflag {
+            b'x' | b'g' | b'L' => header_size,
+            _ => pax_size.take().unwrap_or(header_size),
+        };
+        let data_offset = pos + TAR_BLOCK;
+        let end = data_offset.checked_add(size).ok_or("tar: tamano invalido.")?;
+        if end > raw.len() {
+            return Err("tar: archivo truncado.");
+        }
+        pos = data_offset + padded(size);
+
+        match typeflag {
+            b'L' => {
+                long_name = Some(String::from_utf8_lossy(field_str(&raw[data_offset..end])).into_owned());
+                continue;
+            }
+            b'x' => {
+                let (path, size) = parse_pax(&raw[data_offset..end]);
+                if path.is_some() {
+                    long_name = path;
+                }
+                pax_size = size;
+                continue;
+            }
+            b'g' => continue,
+            _ => {}
+        }
+
+        let path = long_name.take().unwrap_or_else(|| {
+            let name = String::from_utf8_lossy(field_str(&block[0..100]));
+            let prefix = field_str(&block[345..500]);
+            if &block[257..262] == b"ustar" && !prefix.is_empty() {
+                alloc::format!("{}/{}", String::from_utf8_lossy(prefix), name)
+            } else {
+                name.into_owned()
+            }
+        });
+        let kind = match typeflag {
+            b'0' | 0 | b'7' => {
+                if path.ends_with('/') {
+                    EntryKind::Directory
+                } else {
+                    EntryKind::File
+                }
+            }
+            b'5' => EntryKind::Directory,
+            _ => EntryKind::Other,
+        };
+        entries.push(ArchiveEntry {
+            path,
+            kind,
+            size: if kind == EntryKind::File { size } else { 0 },
+            mtime: parse_octal(&block[136..148]).unwrap_or(0).min(u32::MAX as u64) as u32,
+            offset: data_offset,
+            stored_size: size,
+            method: 0,
+            crc32: None,
+        });
+    }
+    Ok(entries)
+}
+
+fn put_octal(field: &mut [u8], value: u64) {
+    // Width minus the terminating NUL.
+    let digits = field.len() - 1;
+    let mut v = value;
+    for i in (0..digits).rev() {
+        field[i] = b'0' + (v & 7) as u8;
+        v >>= 3;
+    }
+    field[digits] = 0;
+}
+
+/// Builds a ustar archive in memory.
+pub struct TarWriter {
+    out: Vec<u8>,
+}
+
+impl Default for TarWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TarWriter {
+    pub fn new() -> Self {
+        Self { out: Vec::new() }
+    }
+
+    /// Split a path into ustar `prefix` and `name`, if it fits.
+    fn split_path(path: &str) -> Option<(&str, &str)> {
+        if path.len() <= TAR_NAME_LEN {
+            return Some(("", path));
+        }
+        path.match_indices('/')
+            .map(|(i, _)| (&path[..i], &path[i + 1..]))
+            .find(|(prefix, name)| prefix.len() <= TAR_PREFIX_LEN && name.len() <= TAR_NAME_LEN && !name.is_empty())
+    }
+
+    fn header(&mut self, path: &str, typeflag: u8, size: usize, mtime: u32) {
+        let (prefix, name) = match Self::split_path(path) {
+            Some(split) => split,
+            None => {
+                // Too long for ustar: GNU long-name record first.
+                let mut long = Vec::from(path.as_bytes());
+                long.push(0);
+                self.header(TAR_GNU_LONGLINK, b'L', long.len(), 0);
+                self.data(long.as_slice());
+                let mut cut = path.len().saturating_sub(TAR_NAME_LEN);
+                while !path.is_char_boundary(cut) {
+                    cut += 1;
+                }
+                ("", &path[cut..])
+            }
+        };
+        let mut block = [0u8; TAR_BLOCK];
+        let name = name.as_bytes();
+        block[..name.len().min(TAR_NAME_LEN)].copy_from_slice(&name[..name.len().min(TAR_NAME_LEN)]);
+        put_octal(&mut block[100..108], if typeflag == b'5' { 0o755 } else { 0o644 });
+        put_octal(&mut block[108..116], 0);
+        put_octal(&mut block[116..124], 0);
+        put_octal(&mut block[124..136], size as u64);
+        put_octal(&mut block[136..148], mtime as u64);
+        block[156] = typeflag;
+        block[257..263].copy_from_slice(b"ustar\0");
+        block[263..265].copy_from_slice(b"00");
+        block[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+        block[148..156].fill(b' ');
+        let sum = checksum(&block);
+        put_octal(&mut block[148..155], sum);
+        block[155] = b' ';
+        self.out.extend_from_slice(&block);
+    }
+
+    fn data(&mut self, data: &[u8]) {
+        self.out.extend_from_slice(data);
+        self.out.resize(self.out.len() + padded(data.len()) - data.len(), 0);
+    }
+
+    pub fn add_file(&mut self, path: &str, data: &[u8], mtime: u32) {
+        self.header(path.trim_start_matches('/'), b'0', data.len(), mtime);
+        self.data(data);
+    }
+
+    pub fn add_dir(&mut self, path: &str, mtime: u32) {
+        let mut path = String::from(path.trim_matches('/'));
+        path.push('/');
+        self.header(path.as_str(), b'5', 0, mtime);
+    }
+
+    /// End-of-archive marker (two zero blocks) and the finished archive.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.out.resize(self.out.len() + 2 * TAR_BLOCK, 0);
+        self.out
+    }
+}
@@ -0,0 +1,283 @@
+//! PKZIP reader and writer (APPNOTE 6.3): stored and DEFLATE members, no
+//! ZIP64, no encryption.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use miniz_oxide::inflate::decompress_to_vec_with_limit;
+
+use super::{ArchiveEntry, EntryKind};
+
+const ZIP_LOCAL_SIG: u32 = 0x0403_4B50;
+const ZIP_CENTRAL_SIG: u32 = 0x0201_4B50;
+const ZIP_EOCD_SIG: u32 = 0x0605_4B50;
+const ZIP_EOCD_LEN: usize = 22;
+const ZIP_LOCAL_HEADER_LEN: usize = 30;
+const ZIP_CENTRAL_HEADER_LEN: usize = 46;
+const ZIP_FLAG_ENCRYPTED: u16 = 0x0001;
+const ZIP_FLAG_UTF8: u16 = 0x0800;
+pub const ZIP_METHOD_STORE: u16 = 0;
+pub const ZIP_METHOD_DEFLATE: u16 = 8;
+/// "Version needed to extract" for DEFLATE members.
+const ZIP_VERSION: u16 = 20;
+
+fn u16_at(raw: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(raw.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(raw: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(raw.get(at..at + 4)?.try_into().ok()?))
+}
+
+pub fn is_zip(raw: &[u8]) -> bool {
+    u32_at(raw, 0).is_some_and(|sig| sig == ZIP_LOCAL_SIG || sig == ZIP_EOCD_SIG)
+}
+
+fn days_from_civil(year: i32, month: u8, day: u8) -> i64 {
+    let y = year - if month <= 2 { 1 } else { 0 };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let m = month as i32;
+    let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + day as i32 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    (era as i64) * 146_097 + (doe as i64) - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i32, u8, u8) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = mp + if mp < 10 { 3 } else { -9 };
+    let year = y + if month <= 2 { 1 } else { 0 };
+    (year as i32, month as u8, day as u8)
+}
+
+/// MS-DOS date/time fields (local time, 2 s resolution) to Unix seconds.
+pub(super) fn dos_to_unix(time: u16, date: u16) -> u32 {
+    let year = 1980 + (date >> 9) as i32;
+    let month = ((date >> 5) & 0x0F).clamp(1, 12) as u8;
+    let day = (date & 0x1F).max(1) as u8;
+    let secs = (time >> 11) as i64 * 3600 + ((time >> 5) & 0x3F) as i64 * 60 + (time & 0x1F) as i64 * 2;
+    (days_from_civil(year, month, day) * 86_400 + secs).clamp(0, u32::MAX as i64) as u32
+}
+
+fn unix_to_dos(unix: u32) -> (u16, u16) {
+    let days = unix as i64 / 86_400;
+    let secs = unix as i64 % 86_400;
+    let (year, month, day) = civil_from_days(days);
+    if year < 1980 {
+        // DOS dates start in 1980.
+        return (0, (1 << 5) | 1);
+    }
+    let time = ((secs / 3600) << 11 | ((secs / 60) % 60) << 5 | (secs % 60) / 2) as u16;
+    let date = (((year - 1980).min(127) as u16) << 9) | ((month as u16) << 5) | day as u16;
+    (time, date)
+}
+
+fn find_eocd(raw: &[u8]) -> Option<usize> {
+    if raw.len() < ZIP_EOCD_LEN {
+        return None;
+    }
+    // The EOCD is followed only by its comment (at most 64 KiB).
+    let last = raw.len() - ZIP_EOCD_LEN;
+    let first = last.saturating_sub(u16::MAX as usize);
+    (first..=last).rev().find(|&pos| u32_at(raw, pos) == Some(ZIP_EOCD_SIG))
+}
+
+/// Entries of the central directory and its offset in `raw`. Entry offsets
+/// point at the local headers.
+pub fn read_central_directory(raw: &[u8]) -> Result<(Vec<ArchiveEntry>, usize), &'static str> {
+    let eocd = find_eocd(raw).ok_or("ZIP sin directorio central.")?;
+    let total = u16_at(raw, eocd + 10).ok_or("ZIP corrupto (EOCD).")? as usize;
+    let central_size = u32_at(raw, eocd + 12).ok_or("ZIP corrupto (EOCD).")?;
+    let central_offset = u32_at(raw, eocd + 16).ok_or("ZIP corrupto (EOCD).")?;
+    if total == 0xFFFF || central_size == u32::MAX || central_offset == u32::MAX {
+        return Err("ZIP64 no soportado.");
+    }
+    let central_offset = central_offset as usize;
+    if central_offset.saturating_add(central_size as usize) > eocd {
+        return Err("ZIP corrupto (directorio central fuera de rango).");
+    }
+
+    let mut entries = Vec::with_capacity(total);
+    let mut pos = central_offset;
+    for _ in 0..total {
+        if u32_at(raw, pos) != Some(ZIP_CENTRAL_SIG) {
+            return Err("ZIP corrupto (entrada central).");
+        }
+        let header = raw.get(pos..pos + ZIP_CENTRAL_HEADER_LEN).ok_or("ZIP corrupto (entrada central).")?;
+        let flags = u16_at(header, 8).unwrap_or(0);
+        let method = u16_at(header, 10).unwrap_or(0);
+        let time = u16_at(header, 12).unwrap_or(0);
+        let date = u16_at(header, 14).unwrap_or(0);
+        let crc = u32_at(header, 16).unwrap_or(0);
+        let compressed = u32_at(header, 20).unwrap_or(0);
+        let size = u32_at(header, 24).unwrap_or(0);
+        let name_len = u16_at(header, 28).unwrap_or(0) as usize;
+        let extra_len = u16_at(header, 30).unwrap_or(0) as usize;
+        let comment_len = u16_at(header, 32).unwrap_or(0) as usize;
+        let local = u32_at(header, 42).unwrap_or(0);
+        if compressed == u32::MAX || size == u32::MAX || local == u32::MAX {
+            return Err("ZIP64 no soportado.");
+        }
+        let name_start = pos + ZIP_CENTRAL_HEADER_LEN;
+        let name = raw.get(name_start..name_start + name_len).ok_or("ZIP corrupto (nombre).")?;
+        // Names are CP437 unless bit 11 says UTF-8; plain ASCII is the same in both.
+        let path: String = if flags & ZIP_FLAG_UTF8 != 0 || name.is_ascii() {
+            String::from_utf8_lossy(name).into_owned()
+        } else {
+            name.iter().map(|&b| if b.is_ascii() { b as char } else { '_' }).collect()
+        };
+        let kind = if path.ends_with('/') || path.ends_with('\\') { EntryKind::Directory } else { EntryKind::File };
+        entries.push(ArchiveEntry {
+            path,
+            kind,
+            size: size as usize,
+            mtime: dos_to_unix(time, date),
+            offset: local as usize,
+            stored_size: compressed as usize,
+            method: if flags & ZIP_FLAG_ENCRYPTED != 0 { u16::MAX } else { method },
+            crc32: Some(crc),
+        });
+        pos = name_start + name_len + extra_len + comment_len;
+    }
+    Ok((entries, central_offset))
+}
+
+/// Compressed bytes of `entry`, located through its local header (whose
+/// extra field may differ in length from the central one).
+pub fn entry_payload<'a>(raw: &'a [u8], entry: &ArchiveEntry) -> Result<&'a [u8], &'static str> {
+    if u32_at(raw, entry.offset) != Some(ZIP_LOCAL_SIG) {
+        return Err("ZIP corrupto (cabecera local).");
+    }
+    let name_len = u16_at(raw, entry.offset + 26).ok_or("ZIP corrupto (cabecera local).")? as usize;
+    let extra_len = u16_at(raw, entry.offset + 28).ok_or("ZIP corrupto (cabecera local).")? as usize;
+    let start = entry.offset + ZIP_LOCAL_HEADER_LEN + name_len + extra_len;
+    raw.get(start..start.saturating_add(entry.stored_size)).ok_or("ZIP truncado.")
+}
+
+/// Uncompressed contents of `entry`, with its CRC-32 checked.
+pub fn read_entry(raw: &[u8], entry: &ArchiveEntry, max_output: usize) -> Result<Vec<u8>, &'static str> {
+    if entry.size > max_output {
+        return Err("entrada ZIP demasiado grande.");
+    }
+    let payload = entry_payload(raw, entry)?;
+    let data = match entry.method {
+        ZIP_METHOD_STORE => payload.to_vec(),
+        ZIP_METHOD_DEFLATE => {
+            decompress_to_vec_with_limit(payload, entry.size.max(1)).map_err(|_| "datos DEFLATE invalidos.")?
+        }
+        u16::MAX => return Err("entrada ZIP cifrada."),
+        _ => return Err("metodo de compresion ZIP no soportado."),
+    };
+    if data.len() != entry.size || entry.crc32.is_some_and(|crc| crc != crate::compress::crc32(data.as_slice())) {
+        return Err("CRC o tamano de la entrada ZIP no coincide.");
+    }
+    Ok(data)
+}
+
+/// Builds a zip archive in memory. Members are deflated unless that would
+/// not make them smaller.
+pub struct ZipWriter {
+    out: Vec<u8>,
+    central: Vec<u8>,
+    count: usize,
+    level: u8,
+}
+
+impl ZipWriter {
+    pub fn new(level: u8) -> Self {
+        Self {
+            out: Vec::new(),
+            central: Vec::new(),
+            count: 0,
+            level,
+        }
+    }
+
+    fn add(&mut self, path: &str, data: &[u8], mtime: u32) -> Result<(), &'static str> {
+        if self.count >= u16::MAX as usize - 1 {
+            return Err("demasiadas entradas para ZIP sin ZIP64.");
+        }
+        if path.len() > u16::MAX as usize || data.len() >= u32::MAX as usize {
+            return Err("entrada demasiado grande para ZIP sin ZIP64.");
+        }
+        let packed = crate::compress::deflate(data, self.level);
+        let (method, body) = if !data.is_empty() && packed.len() < data.len() {
+            (ZIP_METHOD_DEFLATE, packed.as_slice())
+        } else {
+            (ZIP_METHOD_STORE, data)
+        };
+        let offset = self.out.len();
+        if offset + ZIP_LOCAL_HEADER_LEN + path.len() + body.len() >= u32::MAX as usize {
+            return Err("archivo demasiado grande para ZIP sin ZIP64.");
+        }
+        let (time, date) = unix_to_dos(mtime);
+        let crc = crate::compress::crc32(data);
+        let flags = if path.is_ascii() { 0 } else { ZIP_FLAG_UTF8 };
+
+        // Fields shared by the local and central headers, from "version needed".
+        let mut common = Vec::with_capacity(26);
+        common.extend_from_slice(&ZIP_VERSION.to_le_bytes());
+        common.extend_from_slice(&flags.to_le_bytes());
+        common.extend_from_slice(&method.to_le_bytes());
+        common.extend_from_slice(&time.to_le_bytes());
+        common.extend_from_slice(&date.to_le_bytes());
+        common.extend_from_slice(&crc.to_le_bytes());
+        common.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(path.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+
+        self.out.extend_from_slice(&ZIP_LOCAL_SIG.to_le_bytes());
+        self.out.extend_from_slice(common.as_slice());
+        self.out.extend_from_slice(path.as_bytes());
+        self.out.extend_from_slice(body);
+
+        let external_attr: u32 = if path.ends_with('/') { 0x10 } else { 0 };
+        self.central.extend_from_slice(&ZIP_CENTRAL_SIG.to_le_bytes());
+        self.central.extend_from_slice(&ZIP_VERSION.to_le_bytes());
+        self.central.extend_from_slice(common.as_slice());
+        self.central.extend_from_slice(&0u16.to_le_bytes()); // comment
+        self.central.extend_from_slice(&0u16.to_le_bytes()); // disk
+        self.central.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+        self.central.extend_from_slice(&external_attr.to_le_bytes());
+        self.central.extend_from_slice(&(offset as u32).to_le_bytes());
+        self.central.extend_from_slice(path.as_bytes());
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn add_file(&mut self, path: &str, data: &[u8], mtime: u32) -> Result<(), &'static str> {
+        self.add(path.trim_start_matches('/'), data, mtime)
+    }
+
+    pub fn add_dir(&mut self, path: &str, mtime: u32) -> Result<(), &'static str> {
+        let mut path = String::from(path.trim_matches('/'));
+        path.push('/');
+        self.add(path.as_str(), &[], mtime)
+    }
+
+    pub fn finish(mut self) -> Result<Vec<u8>, &'static str> {
+        let central_offset = self.out.len();
+        if central_offset + self.central.len() >= u32::MAX as usize {
+            return Err("archivo demasiado grande para ZIP sin ZIP64.");
+        }
+        let central_len = self.central.len();
+        self.out.append(&mut self.central);
+        self.out.extend_from_slice(&ZIP_EOCD_SIG.to_le_bytes());
+        self.out.extend_from_slice(&0u16.to_le_bytes());
+        self.out.extend_from_slice(&0u16.to_le_bytes());
+        self.out.extend_from_slice(&(self.count as u16).to_le_bytes());
+        self.out.extend_from_slice(&(self.count as u16).to_le_bytes());
+        self.out.extend_from_slice(&(central_len as u32).to_le_bytes());
+        self.out.extend_from_slice(&(central_offset as u32).to_le_bytes());
+        self.out.extend_from_slice(&0u16.to_le_bytes());
+        Ok(self.out)
+    }
+}
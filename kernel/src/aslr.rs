@@ -0,0 +1,69 @@
+//! Address space layout randomization for Linux processes.
+//!
+//! Every exec draws new bases from `crate::random`: the mmap area moves up by
+//! up to 4 GiB, the brk heap down by up to 1 GiB, and the initial stack
+//! pointer down by up to 64 KiB inside a stack grown to match. ELF images stay
+//! where the kernel heap puts them. `security.aslr false` keeps the fixed
+//! layout for debugging, starting with the next exec.
+
+pub const KEY: &str = "security.aslr";
+pub const MMAP_SHIFT_MAX: u64 = 4 << 30;
+pub const BRK_GAP_MAX: u64 = 1 << 30;
+pub const STACK_GAP_MAX: usize = 64 * 1024;
+const PAGE_SIZE: u64 = 4096;
+/// The stack pointer keeps the ABI's 16-byte alignment.
+const STACK_ALIGN: u64 = 16;
+
+pub fn enabled() -> bool {
+    crate::config::get_bool(KEY, true)
+}
+
+/// Random multiple of `align` below `max`; 0 with ASLR off.
+fn offset(max: u64, align: u64) -> u64 {
+    if !enabled() || max < align {
+        return 0;
+    }
+    (crate::random::next_u64() % (max / align)) * align
+}
+
+/// How far above the fixed base the mmap area starts.
+pub fn mmap_shift() -> u64 {
+    offset(MMAP_SHIFT_MAX, PAGE_SIZE)
+}
+
+/// How far below its fixed place the brk region starts.
+pub fn brk_gap() -> u64 {
+    offset(BRK_GAP_MAX, PAGE_SIZE)
+}
+
+/// Bytes left unused at the top of the initial stack.
+pub fn stack_gap() -> usize {
+    offset(STACK_GAP_MAX as u64, STACK_ALIGN) as usize
+}
+
+crate::selftest::kernel_tests! {
+    "aslr";
+
+    fn offsets_aligned_and_bounded() {
+        for _ in 0..64 {
+            let value = offset(MMAP_SHIFT_MAX, PAGE_SIZE);
+            crate::selftest::ensure(value % PAGE_SIZE == 0 && value < MMAP_SHIFT_MAX, "mmap")?;
+            let value = offset(STACK_GAP_MAX as u64, STACK_ALIGN);
+            crate::selftest::ensure(value % STACK_ALIGN == 0 && value < STACK_GAP_MAX as u64, "pila")?;
+        }
+        Ok(())
+    }
+
+    fn offsets_vary() {
+        if !enabled() {
+            return Ok(());
+        }
+        let first = brk_gap();
+        let varied = (0..16).any(|_| brk_gap() != first);
+        crate::selftest::ensure(varied, "siempre el mismo desplazamiento")
+    }
+
+    fn too_small_range_is_zero() {
+        crate::selftest::ensure_eq(offset(PAGE_SIZE - 1, PAGE_SIZE), 0, "rango menor que la alineacion")
+    }
+}
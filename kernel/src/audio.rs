@@ -1,8 +1,10 @@
 // Intel High Definition Audio (HDA) Controller Driver
-// Supports PCM playback via codec DAC → Pin Widget output path.
+// Supports PCM playback via codec DAC → Pin Widget output path, and
+// microphone capture via input Pin Widget → ADC into a cyclic DMA buffer.
 
-use crate::pci::{PciDevice, read_bar, enable_bus_master};
+use crate::pci::{PciDevice, PciDriver, PciMatch, read_bar, enable_bus_master};
 use crate::println;
+use crate::sync::KernelCell;
 use alloc::vec::Vec;
 use alloc::string::String;
 
@@ -91,6 +93,7 @@ const VERB_SET_AMP_GAIN: u32     = 0x300_00;
 const VERB_SET_POWER_STATE: u32  = 0x705_00;
 const VERB_SET_CONVERTER_FMT: u32= 0x200_00;
 const VERB_GET_CONN_LIST: u32    = 0xF02_00;
+const VERB_SET_CONNECT_SEL: u32  = 0x701_00;
 
 // Parameter IDs
 const PARAM_VENDOR_ID: u32    = 0x00;
@@ -113,6 +116,8 @@ const WIDGET_TYPE_BEEP: u32   = 0x7;
 const PIN_DEV_LINE_OUT: u32   = 0x0;
 const PIN_DEV_SPEAKER: u32    = 0x1;
 const PIN_DEV_HP_OUT: u32     = 0x2;
+const PIN_DEV_LINE_IN: u32    = 0x8;
+const PIN_DEV_MIC_IN: u32     = 0xA;
 
 // ─── BDL Entry ───────────────────────────────────────────────────────────────
 #[derive(Copy, Clone)]
@@ -145,10 +150,25 @@ struct BdlBuffer([BdlEntry; BDL_ENTRIES]);
 #[repr(C, align(4096))]
 struct PcmBuffer([u8; PCM_BUFFER_BYTES]);
 
+// Capture runs forever in a cyclic buffer split in two BDL halves (the
+// controller wants at least two entries); `capture_poll` drains it.
+const CAPTURE_BDL_ENTRIES: usize = 2;
+const CAPTURE_BUFFER_BYTES: usize = 32 * 1024; // ~170 ms of 48kHz stereo 16-bit
+const CAPTURE_STREAM_TAG: u32 = 2;
+
+#[repr(C, align(128))]
+struct CaptureBdl([BdlEntry; CAPTURE_BDL_ENTRIES]);
+
+#[repr(C, align(4096))]
+struct CaptureBuffer([u8; CAPTURE_BUFFER_BYTES]);
+
 static mut CORB_BUF: CorbBuffer = CorbBuffer([0u32; CORB_ENTRIES]);
 static mut RIRB_BUF: RirbBuffer = RirbBuffer([[0u32; 2]; RIRB_ENTRIES]);
 static mut BDL_BUF: BdlBuffer = BdlBuffer([BdlEntry { address: 0, length: 0, ioc: 0 }; BDL_ENTRIES]);
 static mut PCM_BUF: PcmBuffer = PcmBuffer([0u8; PCM_BUFFER_BYTES]);
+static CAPTURE_BDL: KernelCell<CaptureBdl> =
+    KernelCell::new(CaptureBdl([BdlEntry { address: 0, length: 0, ioc: 0 }; CAPTURE_BDL_ENTRIES]));
+static CAPTURE_BUF: KernelCell<CaptureBuffer> = KernelCell::new(CaptureBuffer([0u8; CAPTURE_BUFFER_BYTES]));
 
 // ─── HDA Controller State ────────────────────────────────────────────────────
 
@@ -167,6 +187,9 @@ pub struct HdaController {
     dac_nid: u16,
     pin_nid: u16,
     mixer_nid: u16,
+    adc_nid: u16,     // 0 when the codec has no usable input path
+    mic_pin_nid: u16,
+    mic_is_line_in: bool,
     num_input_streams: u8,
     num_output_streams: u8,
     output_stream_index: u8, // which output stream descriptor to use
@@ -179,6 +202,9 @@ pub struct HdaController {
     pub pcm_total_bytes: usize,
     pub pcm_written: usize,
     pub volume: u8, // 0..127
+    // Capture state
+    pub capture_running: bool,
+    capture_read_pos: usize, // byte offset in CAPTURE_BUF already handed out
 }
 
 pub static mut GLOBAL_HDA: HdaController = HdaController {
@@ -188,6 +214,9 @@ pub static mut GLOBAL_HDA: HdaController = HdaController {
     dac_nid: 0,
     pin_nid: 0,
     mixer_nid: 0,
+    adc_nid: 0,
+    mic_pin_nid: 0,
+    mic_is_line_in: false,
     num_input_streams: 0,
     num_output_streams: 0,
     output_stream_index: 0,
@@ -199,6 +228,8 @@ pub static mut GLOBAL_HDA: HdaController = HdaController {
     pcm_total_bytes: 0,
     pcm_written: 0,
     volume: 100,
+    capture_running: false,
+    capture_read_pos: 0,
 };
 
 // ─── MMIO Helpers ────────────────────────────────────────────────────────────
@@ -245,6 +276,12 @@ fn delay_us(us: u64) {
 
 // ─── Public API ──────────────────────────────────────────────────────────────
 
+pub static PCI_DRIVER: PciDriver = PciDriver {
+    name: "hda",
+    ids: &[PciMatch::class(0x04, 0x03), PciMatch::class(0x04, 0x01)],
+    probe: init,
+};
+
 pub fn init(device: PciDevice) {
     let bar0 = unsafe { read_bar(device.bus, device.slot, device.func, 0) };
     let base = match bar0 {
@@ -348,7 +385,20 @@ pub fn init(device: PciDevice) {
     hda_log("HDA: Step 6 - Configure output...");
     unsafe { hda_configure_output(hda); }
 
+    // Step 7: Input path (optional; playback works without it)
+    if hda.adc_nid != 0 && hda.mic_pin_nid != 0 && hda.num_input_streams != 0 {
+        unsafe { hda_configure_input(hda); }
+        hda_log(alloc::format!(
+            "HDA: Step 7 - Input: ADC=nid{} PIN=nid{} ({})",
+            hda.adc_nid, hda.mic_pin_nid, capture_input_name()
+        ).as_str());
+    } else {
+        hda_log("HDA: Step 7 - No input path, capture disabled.");
+    }
+
     hda.state = HdaState::Ready;
+    let node = crate::device::add_class_device(Some(crate::device::pci_function(&device)), "card", "sound", "hda");
+    crate::device::set_live(node, "state", || alloc::string::String::from(status_text()));
     hda_log("HDA: Driver ready.");
 }
 
@@ -426,6 +476,77 @@ pub fn status_text() -> &'static str {
     }
 }
 
+// ─── Capture API ─────────────────────────────────────────────────────────────
+
+/// Format of everything `capture_poll` returns: 16-bit LE, interleaved.
+pub const CAPTURE_RATE: u32 = 48000;
+pub const CAPTURE_CHANNELS: u16 = 2;
+
+/// Whether the codec exposes a microphone or line-in path.
+pub fn has_capture() -> bool {
+    let hda = unsafe { &GLOBAL_HDA };
+    is_ready() && hda.adc_nid != 0 && hda.mic_pin_nid != 0 && hda.num_input_streams != 0
+}
+
+/// "Microfono" or "Entrada de linea", for status lines.
+pub fn capture_input_name() -> &'static str {
+    let hda = unsafe { &GLOBAL_HDA };
+    if hda.mic_pin_nid == 0 {
+        "Ninguna"
+    } else if hda.mic_is_line_in {
+        "Entrada de linea"
+    } else {
+        "Microfono"
+    }
+}
+
+/// Start the input stream; it keeps running until `capture_stop`.
+pub fn capture_start() -> Result<(), &'static str> {
+    if !has_capture() {
+        return Err("sin entrada de audio");
+    }
+    let hda = unsafe { &mut GLOBAL_HDA };
+    if !hda.capture_running {
+        unsafe { hda_start_capture(hda); }
+        hda.capture_running = true;
+        hda.capture_read_pos = 0;
+    }
+    Ok(())
+}
+
+pub fn capture_stop() {
+    let hda = unsafe { &mut GLOBAL_HDA };
+    if hda.capture_running {
+        unsafe { hda_stop_capture(hda); }
+        hda.capture_running = false;
+    }
+}
+
+/// Append the bytes the controller wrote since the last call to `out`.
+/// Must run more often than the buffer wraps (~170 ms) or audio is lost.
+pub fn capture_poll(out: &mut Vec<u8>) -> usize {
+    let hda = unsafe { &mut GLOBAL_HDA };
+    if !hda.capture_running {
+        return 0;
+    }
+    let lpib = unsafe { mmio_read32(hda.mmio_base, SD_BASE_OFFSET + SD_LPIB) } as usize;
+    // Whole stereo frames only.
+    let pos = (lpib % CAPTURE_BUFFER_BYTES) & !3;
+    let start = hda.capture_read_pos;
+    // SAFETY: capture is started and drained from the kernel thread only.
+    let buf = unsafe { &CAPTURE_BUF.get().0 };
+    let added = if pos >= start {
+        out.extend_from_slice(&buf[start..pos]);
+        pos - start
+    } else {
+        out.extend_from_slice(&buf[start..]);
+        out.extend_from_slice(&buf[..pos]);
+        CAPTURE_BUFFER_BYTES - start + pos
+    };
+    hda.capture_read_pos = pos;
+    added
+}
+
 // ─── Internal Implementation ─────────────────────────────────────────────────
 
 unsafe fn hda_reset_controller(base: u64) -> bool {
@@ -587,6 +708,9 @@ unsafe fn hda_discover_output_path(hda: &mut HdaController) -> bool {
     let mut dac_candidates: Vec<u16> = Vec::new();
     let mut pin_candidates: Vec<u16> = Vec::new();
     let mut mixer_candidates: Vec<u16> = Vec::new();
+    let mut adc_candidates: Vec<u16> = Vec::new();
+    let mut mic_candidates: Vec<u16> = Vec::new();
+    let mut line_in_candidates: Vec<u16> = Vec::new();
 
     for nid in widget_start..(widget_start + widget_count) {
         let wcap = hda_send_verb(hda, nid, VERB_GET_PARAM | PARAM_AUDIO_WIDGET_CAP);
@@ -596,6 +720,9 @@ unsafe fn hda_discover_output_path(hda: &mut HdaController) -> bool {
             WIDGET_TYPE_OUTPUT => {
                 dac_candidates.push(nid);
             }
+            WIDGET_TYPE_INPUT => {
+                adc_candidates.push(nid);
+            }
             WIDGET_TYPE_PIN => {
                 // Check pin default configuration for output capability
                 let pin_cfg = hda_send_verb(hda, nid, 0xF1C_00); // GET_CONFIG_DEFAULT
@@ -606,6 +733,10 @@ unsafe fn hda_discover_output_path(hda: &mut HdaController) -> bool {
                     || default_device == PIN_DEV_HP_OUT
                 {
                     pin_candidates.push(nid);
+                } else if default_device == PIN_DEV_MIC_IN {
+                    mic_candidates.push(nid);
+                } else if default_device == PIN_DEV_LINE_IN {
+                    line_in_candidates.push(nid);
                 }
             }
             WIDGET_TYPE_MIXER => {
@@ -634,9 +765,55 @@ unsafe fn hda_discover_output_path(hda: &mut HdaController) -> bool {
     hda.pin_nid = pin_nid;
     hda.mixer_nid = mixer_nid;
 
+    // Input path: prefer a microphone jack, fall back to line in.
+    hda.adc_nid = adc_candidates.first().copied().unwrap_or(0);
+    hda.mic_is_line_in = mic_candidates.is_empty();
+    hda.mic_pin_nid = mic_candidates.first().or(line_in_candidates.first()).copied().unwrap_or(0);
+
     true
 }
 
+/// Index of `target` in the short-form connection list of `nid`.
+unsafe fn hda_connection_index(hda: &mut HdaController, nid: u16, target: u16) -> Option<u32> {
+    let len_param = hda_send_verb(hda, nid, VERB_GET_PARAM | PARAM_CONN_LIST_LEN);
+    if len_param & 0x80 != 0 {
+        return None; // long form lists are not used by the codecs we target
+    }
+    let len = len_param & 0x7F;
+    for index in 0..len {
+        let entries = hda_send_verb(hda, nid, VERB_GET_CONN_LIST | (index & !3));
+        if (entries >> ((index & 3) * 8)) & 0xFF == target as u32 {
+            return Some(index);
+        }
+    }
+    None
+}
+
+unsafe fn hda_configure_input(hda: &mut HdaController) {
+    hda_send_verb(hda, hda.adc_nid, VERB_SET_POWER_STATE | 0x00);
+    delay_us(500);
+    hda_send_verb(hda, hda.mic_pin_nid, VERB_SET_POWER_STATE | 0x00);
+    delay_us(500);
+
+    // Pin Widget Control: IN enable (bit 5), mic bias VREF 80% (0b100)
+    let vref = if hda.mic_is_line_in { 0x00 } else { 0x04 };
+    hda_send_verb(hda, hda.mic_pin_nid, VERB_SET_PIN_WIDGET | 0x20 | vref);
+    delay_us(100);
+
+    // Route the pin into the ADC when it is a direct connection; codecs
+    // with a mixer or selector in between keep their default routing.
+    if let Some(index) = hda_connection_index(hda, hda.adc_nid, hda.mic_pin_nid) {
+        hda_send_verb(hda, hda.adc_nid, VERB_SET_CONNECT_SEL | index);
+        delay_us(100);
+    }
+
+    // Input amp on pin and ADC: [14] input, [13:12] left+right, unmuted, gain
+    hda_send_verb(hda, hda.mic_pin_nid, 0x370_00 | 0x40);
+    delay_us(100);
+    hda_send_verb(hda, hda.adc_nid, 0x370_00 | 0x40);
+    delay_us(100);
+}
+
 unsafe fn hda_configure_output(hda: &mut HdaController) {
     // Power on DAC
     hda_send_verb(hda, hda.dac_nid, VERB_SET_POWER_STATE | 0x00);
@@ -710,10 +887,8 @@ unsafe fn hda_output_sd_base(hda: &HdaController) -> u32 {
     SD_BASE_OFFSET + (input_count + hda.output_stream_index as u32) * SD_SIZE
 }
 
-unsafe fn hda_start_playback(hda: &mut HdaController, data_len: usize) {
-    let base = hda.mmio_base;
-    let sd_base = hda_output_sd_base(hda);
-
+/// Stop a stream descriptor and cycle it through SRST.
+unsafe fn hda_reset_stream(base: u64, sd_base: u32) {
     // Stop stream first
     let ctl = mmio_read32(base, sd_base + SD_CTL) & 0x00FFFFFF;
     mmio_write32(base, sd_base + SD_CTL, ctl & !SD_CTL_RUN);
@@ -739,6 +914,12 @@ unsafe fn hda_start_playback(hda: &mut HdaController, data_len: usize) {
 
     // Clear status bits
     mmio_write8(base, sd_base + SD_STS, 0x1C);
+}
+
+unsafe fn hda_start_playback(hda: &mut HdaController, data_len: usize) {
+    let base = hda.mmio_base;
+    let sd_base = hda_output_sd_base(hda);
+    hda_reset_stream(base, sd_base);
 
     // Setup BDL — single entry pointing to entire PCM buffer
     let pcm_phys = &PCM_BUF as *const PcmBuffer as u64;
@@ -800,3 +981,52 @@ unsafe fn hda_stop_playback(hda: &mut HdaController) {
     // Silence: reset DAC stream/channel
     hda_send_verb(hda, hda.dac_nid, VERB_SET_CHAN_STREAM | 0x00);
 }
+
+/// Input stream descriptor 0 is used for capture, always 48kHz stereo.
+unsafe fn hda_start_capture(hda: &mut HdaController) {
+    let base = hda.mmio_base;
+    let sd_base = SD_BASE_OFFSET;
+    hda_reset_stream(base, sd_base);
+
+    let buf = CAPTURE_BUF.get_mut();
+    let bdl = CAPTURE_BDL.get_mut();
+    let buf_phys = buf as *const CaptureBuffer as u64;
+    let half = CAPTURE_BUFFER_BYTES / CAPTURE_BDL_ENTRIES;
+    for (i, entry) in bdl.0.iter_mut().enumerate() {
+        *entry = BdlEntry {
+            address: buf_phys + (i * half) as u64,
+            length: half as u32,
+            ioc: 0, // polled, no interrupts
+        };
+    }
+    buf.0.fill(0);
+
+    let bdl_phys = bdl as *const CaptureBdl as u64;
+    mmio_write32(base, sd_base + SD_BDPL, bdl_phys as u32);
+    mmio_write32(base, sd_base + SD_BDPU, (bdl_phys >> 32) as u32);
+    mmio_write32(base, sd_base + SD_CBL, CAPTURE_BUFFER_BYTES as u32);
+    mmio_write16(base, sd_base + SD_LVI, (CAPTURE_BDL_ENTRIES - 1) as u16);
+
+    let fmt = hda_stream_format(CAPTURE_RATE, CAPTURE_CHANNELS, 16);
+    mmio_write16(base, sd_base + SD_FMT, fmt);
+
+    hda_send_verb(hda, hda.adc_nid, VERB_SET_CHAN_STREAM | ((CAPTURE_STREAM_TAG << 4) & 0xFF));
+    delay_us(100);
+    hda_send_verb(hda, hda.adc_nid, VERB_SET_CONVERTER_FMT | (fmt as u32));
+    delay_us(100);
+
+    let ctl_val = SD_CTL_RUN | (CAPTURE_STREAM_TAG << SD_CTL_STREAM_SHIFT);
+    mmio_write32(base, sd_base + SD_CTL, ctl_val & 0x00FFFFFF);
+}
+
+unsafe fn hda_stop_capture(hda: &mut HdaController) {
+    let base = hda.mmio_base;
+    let sd_base = SD_BASE_OFFSET;
+
+    let ctl = mmio_read32(base, sd_base + SD_CTL) & 0x00FFFFFF;
+    mmio_write32(base, sd_base + SD_CTL, ctl & !SD_CTL_RUN);
+    delay_us(100);
+    mmio_write8(base, sd_base + SD_STS, 0x1C);
+
+    hda_send_verb(hda, hda.adc_nid, VERB_SET_CHAN_STREAM | 0x00);
+}
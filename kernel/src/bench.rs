@@ -0,0 +1,382 @@
+//! `bench`: fixed workloads whose numbers can be compared across builds.
+//!
+//! - `mem`: copying an 8 MiB buffer, and allocating and freeing blocks of
+//!   16 bytes to 4 KiB.
+//! - `fs`: writing an 8 MiB file to `\REDUXOS` on the boot volume, reading
+//!   it back in 64 KiB chunks and in 4 KiB reads at random offsets.
+//! - `net`: an HTTP download whose body is dropped (`net::bench`).
+//! - `gui`: full redraws of the desktop, only from a desktop terminal.
+//!
+//! The sizes, counts and random offsets (a fixed seed) never change, so two
+//! runs on the same machine only differ by the build. `--json` also writes
+//! the results, with the git revision of the build, to `\REDUXOS\BENCH.JSN`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const DIR: &str = "REDUXOS";
+const JSON_FILE: &str = "BENCH.JSN";
+const SCRATCH_FILE: &str = "BENCH.DAT";
+const MIB: u64 = 1024 * 1024;
+const COPY_BYTES: usize = 8 * 1024 * 1024;
+const COPY_ROUNDS: usize = 16;
+const ALLOC_OPS: usize = 200_000;
+const ALLOC_SLOTS: usize = 64;
+const FS_BYTES: usize = 8 * 1024 * 1024;
+const FS_CHUNK: usize = 64 * 1024;
+const FS_RANDOM_READ: usize = 4096;
+const FS_RANDOM_READS: usize = 256;
+const REDRAW_FRAMES: u32 = 60;
+/// Fixed so every run reads the same offsets.
+const SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Suite {
+    Mem,
+    Fs,
+    Net,
+    Gui,
+}
+
+impl Suite {
+    pub const ALL: [Suite; 4] = [Suite::Mem, Suite::Fs, Suite::Net, Suite::Gui];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Suite::Mem => "mem",
+            Suite::Fs => "fs",
+            Suite::Net => "net",
+            Suite::Gui => "gui",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|suite| suite.name() == name)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Sample {
+    pub name: &'static str,
+    pub value: u64,
+    pub unit: &'static str,
+    pub higher_is_better: bool,
+}
+
+impl Sample {
+    fn higher(name: &'static str, value: u64, unit: &'static str) -> Self {
+        Self {
+            name,
+            value,
+            unit,
+            higher_is_better: true,
+        }
+    }
+
+    fn lower(name: &'static str, value: u64, unit: &'static str) -> Self {
+        Self {
+            name,
+            value,
+            unit,
+            higher_is_better: false,
+        }
+    }
+}
+
+/// What the desktop terminal lends a run: keeping the UI alive during the
+/// download, and a full redraw.
+pub enum Hook {
+    PumpUi,
+    Redraw,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Plan {
+    pub suites: Vec<Suite>,
+    pub json: bool,
+    pub url: Option<String>,
+}
+
+pub const USAGE: &str = "Uso: bench [mem|fs|net [url]|gui|all] [--json]";
+
+pub fn parse(args: &str) -> Result<Plan, &'static str> {
+    let mut plan = Plan {
+        suites: Vec::new(),
+        json: false,
+        url: None,
+    };
+    for word in args.split_whitespace() {
+        if word == "--json" {
+            plan.json = true;
+        } else if word == "all" {
+            plan.suites = Suite::ALL.to_vec();
+        } else if let Some(suite) = Suite::from_name(word) {
+            if !plan.suites.contains(&suite) {
+                plan.suites.push(suite);
+            }
+        } else if plan.suites.last() == Some(&Suite::Net) && plan.url.is_none() && word.contains("://") {
+            plan.url = Some(String::from(word));
+        } else {
+            return Err(USAGE);
+        }
+    }
+    if plan.suites.is_empty() {
+        plan.suites = Suite::ALL.to_vec();
+    }
+    Ok(plan)
+}
+
+/// Arguments for `bench` from a shell line, also taking `membench`,
+/// `fsbench`, `netbench` and `guibench`.
+pub fn command_args(cmd: &str) -> Option<String> {
+    let (verb, rest) = cmd.split_once(' ').unwrap_or((cmd, ""));
+    if verb == "bench" {
+        return Some(String::from(rest));
+    }
+    let suite = Suite::from_name(verb.strip_suffix("bench")?)?;
+    Some(alloc::format!("{} {}", suite.name(), rest))
+}
+
+fn xorshift(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+/// Units of `per` per second for `amount` done in `elapsed_us`.
+fn per_second(amount: u64, per: u64, elapsed_us: u64) -> u64 {
+    (amount as u128 * 1_000_000 / (per.max(1) as u128 * elapsed_us.max(1) as u128)) as u64
+}
+
+fn mem() -> Vec<Sample> {
+    let src = alloc::vec![0x5Au8; COPY_BYTES];
+    let mut dst = alloc::vec![0u8; COPY_BYTES];
+    let start = crate::perf::now_us();
+    for _ in 0..COPY_ROUNDS {
+        dst.copy_from_slice(core::hint::black_box(src.as_slice()));
+        core::hint::black_box(dst.as_mut_slice());
+    }
+    let copy_us = crate::perf::now_us().saturating_sub(start);
+
+    let mut slots: Vec<Option<Vec<u8>>> = (0..ALLOC_SLOTS).map(|_| None).collect();
+    let mut rng = SEED;
+    let start = crate::perf::now_us();
+    for _ in 0..ALLOC_OPS {
+        let r = xorshift(&mut rng);
+        let size = 16 + (r >> 8) as usize % 4081;
+        let mut block = Vec::with_capacity(size);
+        block.push(r as u8);
+        slots[r as usize % ALLOC_SLOTS] = Some(core::hint::black_box(block));
+    }
+    drop(slots);
+    let alloc_us = crate::perf::now_us().saturating_sub(start);
+
+    alloc::vec![
+        Sample::higher(
+            "mem.copy",
+            per_second((COPY_BYTES * COPY_ROUNDS) as u64, MIB, copy_us),
+            "MiB/s"
+        ),
+        Sample::higher("mem.alloc", per_second(ALLOC_OPS as u64, 1, alloc_us), "ops/s"),
+    ]
+}
+
+/// Read the scratch file in `dir` sequentially, then at random offsets:
+/// (sequential, random) microseconds.
+fn read_back(fat: &mut crate::fat32::Fat32, dir: u32) -> Result<(u64, u64), &'static str> {
+    let entry = fat
+        .read_dir_entries(dir)?
+        .into_iter()
+        .find(|entry| entry.valid && entry.matches_name(SCRATCH_FILE))
+        .ok_or("no se encuentra el fichero de prueba")?;
+    let (cluster, size) = (entry.cluster, entry.size as usize);
+    let mut buf = alloc::vec![0u8; FS_CHUNK];
+
+    let start = crate::perf::now_us();
+    let mut offset = 0usize;
+    while offset < size {
+        let n = fat.read_file_range(cluster, size, offset, buf.as_mut_slice())?;
+        if n == 0 {
+            return Err("lectura corta");
+        }
+        offset += n;
+    }
+    let seq_us = crate::perf::now_us().saturating_sub(start);
+
+    let blocks = (size / FS_RANDOM_READ).max(1);
+    let mut rng = SEED;
+    let start = crate::perf::now_us();
+    for _ in 0..FS_RANDOM_READS {
+        let offset = (xorshift(&mut rng) as usize % blocks) * FS_RANDOM_READ;
+        fat.read_file_range(cluster, size, offset, &mut buf[..FS_RANDOM_READ])?;
+    }
+    Ok((seq_us, crate::perf::now_us().saturating_sub(start)))
+}
+
+fn fs() -> Result<Vec<Sample>, &'static str> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    if fat.init_status != crate::fat32::InitStatus::Success {
+        return Err("volumen de arranque no montado");
+    }
+    let dir = fat.ensure_subdirectory(fat.root_cluster, DIR)?;
+    let data: Vec<u8> = (0..FS_BYTES).map(|i| (i * 31) as u8).collect();
+    let start = crate::perf::now_us();
+    fat.write_text_file_in_dir(dir, SCRATCH_FILE, data.as_slice())?;
+    let write_us = crate::perf::now_us().saturating_sub(start);
+    drop(data);
+
+    let measured = read_back(fat, dir);
+    let _ = fat.delete_file_in_dir(dir, SCRATCH_FILE);
+    let (seq_us, random_us) = measured?;
+
+    Ok(alloc::vec![
+        Sample::higher("fs.write", per_second(FS_BYTES as u64, MIB, write_us), "MiB/s"),
+        Sample::higher("fs.seq_read", per_second(FS_BYTES as u64, MIB, seq_us), "MiB/s"),
+        Sample::higher("fs.rand_read", per_second(FS_RANDOM_READS as u64, 1, random_us), "IOPS"),
+    ])
+}
+
+fn net(url: &str, hook: &mut dyn FnMut(Hook)) -> Result<Vec<Sample>, &'static str> {
+    let (bytes, elapsed_us) = crate::net::bench::download(url, &mut || hook(Hook::PumpUi))
+        .ok_or("sin respuesta (red caida, URL invalida o tiempo agotado)")?;
+    Ok(alloc::vec![Sample::higher(
+        "net.http",
+        per_second(bytes as u64, 1024, elapsed_us),
+        "KiB/s"
+    )])
+}
+
+fn gui(hook: &mut dyn FnMut(Hook)) -> Vec<Sample> {
+    let mut total_us = 0u64;
+    let mut worst_us = 0u64;
+    for _ in 0..REDRAW_FRAMES {
+        let start = crate::perf::now_us();
+        hook(Hook::Redraw);
+        let frame_us = crate::perf::now_us().saturating_sub(start);
+        total_us += frame_us;
+        worst_us = worst_us.max(frame_us);
+    }
+    alloc::vec![
+        Sample::lower("gui.redraw", total_us / REDRAW_FRAMES as u64, "us"),
+        Sample::lower("gui.redraw_max", worst_us, "us"),
+        Sample::higher("gui.fps", per_second(REDRAW_FRAMES as u64, 1, total_us), "fps"),
+    ]
+}
+
+pub fn revision() -> &'static str {
+    env!("REDUX_GIT_REV")
+}
+
+pub fn encode_json(revision: &str, cpu: &str, samples: &[Sample]) -> String {
+    let mut out = String::from("{\"revision\":");
+    crate::trace::push_json_str(&mut out, revision);
+    out.push_str(",\"cpu\":");
+    crate::trace::push_json_str(&mut out, cpu);
+    out.push_str(",\"results\":[");
+    for (i, sample) in samples.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str("\n{\"name\":");
+        crate::trace::push_json_str(&mut out, sample.name);
+        out.push_str(alloc::format!(",\"value\":{},\"unit\":", sample.value).as_str());
+        crate::trace::push_json_str(&mut out, sample.unit);
+        out.push_str(if sample.higher_is_better {
+            ",\"better\":\"higher\"}"
+        } else {
+            ",\"better\":\"lower\"}"
+        });
+    }
+    out.push_str("]}\n");
+    out
+}
+
+fn save_json(samples: &[Sample]) -> Result<String, &'static str> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    if fat.init_status != crate::fat32::InitStatus::Success {
+        return Err("volumen de arranque no montado");
+    }
+    let json = encode_json(revision(), crate::hwinfo::cpu_summary().brand.as_str(), samples);
+    let dir = fat.ensure_subdirectory(fat.root_cluster, DIR)?;
+    fat.write_text_file_in_dir(dir, JSON_FILE, json.as_bytes())?;
+    Ok(alloc::format!("\\{}\\{}", DIR, JSON_FILE))
+}
+
+/// Shared implementation of `bench`. `desktop` is `None` from the text
+/// shell, where there is no desktop to redraw.
+pub fn command_lines(args: &str, desktop: Option<&mut dyn FnMut(Hook)>) -> Vec<String> {
+    let plan = match parse(args) {
+        Ok(plan) => plan,
+        Err(usage) => return alloc::vec![String::from(usage)],
+    };
+    let mut noop = |_: Hook| {};
+    let has_desktop = desktop.is_some();
+    let hook: &mut dyn FnMut(Hook) = match desktop {
+        Some(hook) => hook,
+        None => &mut noop,
+    };
+
+    let mut out = alloc::vec![alloc::format!("bench (build {})", revision())];
+    let mut samples = Vec::new();
+    for suite in plan.suites.iter().copied() {
+        let result = match suite {
+            Suite::Mem => Ok(mem()),
+            Suite::Fs => fs(),
+            Suite::Net => net(plan.url.as_deref().unwrap_or(crate::net::bench::DEFAULT_URL), hook),
+            Suite::Gui if has_desktop => Ok(gui(hook)),
+            Suite::Gui => Err("solo desde una terminal del escritorio"),
+        };
+        match result {
+            Ok(results) => {
+                for sample in results.iter() {
+                    out.push(alloc::format!(
+                        "  {:<16}{:>12} {}",
+                        sample.name,
+                        sample.value,
+                        sample.unit
+                    ));
+                }
+                samples.extend(results);
+            }
+            Err(err) => out.push(alloc::format!("  {:<16}{}", suite.name(), err)),
+        }
+    }
+    if plan.json {
+        match save_json(samples.as_slice()) {
+            Ok(path) => out.push(alloc::format!("Resultados en {}", path)),
+            Err(err) => out.push(alloc::format!("bench: {}", err)),
+        }
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "bench";
+
+    fn parses_suites_aliases_and_flags() {
+        let plan = parse("fs net http://h/x --json").map_err(|_| "fs net")?;
+        crate::selftest::ensure_eq(plan.suites, alloc::vec![Suite::Fs, Suite::Net], "suites")?;
+        crate::selftest::ensure_eq(plan.url.as_deref(), Some("http://h/x"), "url")?;
+        crate::selftest::ensure(plan.json, "json")?;
+        crate::selftest::ensure_eq(parse("").map(|p| p.suites.len()), Ok(4), "todas")?;
+        crate::selftest::ensure(parse("mem http://h/").is_err(), "url sin net")?;
+        crate::selftest::ensure_eq(command_args("membench --json"), Some(String::from("mem --json")), "alias")?;
+        crate::selftest::ensure_eq(command_args("bench"), Some(String::new()), "bench")?;
+        crate::selftest::ensure_eq(command_args("cryptobench"), None, "otro comando")
+    }
+
+    fn json_lists_every_sample() {
+        let samples = [
+            Sample::higher("mem.copy", 9000, "MiB/s"),
+            Sample::lower("gui.redraw", 4100, "us"),
+        ];
+        let json = encode_json("abc123", "CPU \"x\"", &samples);
+        crate::selftest::ensure(json.starts_with("{\"revision\":\"abc123\",\"cpu\":\"CPU \\\"x\\\"\""), "cabecera")?;
+        crate::selftest::ensure(
+            json.contains("{\"name\":\"gui.redraw\",\"value\":4100,\"unit\":\"us\",\"better\":\"lower\"}"),
+            "muestra",
+        )?;
+        crate::selftest::ensure_eq(per_second(8 * MIB, MIB, 500_000), 16, "MiB/s")
+    }
+}
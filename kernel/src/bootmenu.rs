@@ -0,0 +1,464 @@
+//! Native boot menu in the style of systemd-boot, shown before the kernel
+//! takes over. It lists the Zenox installs, every system `osprober` finds
+//! (loader entries, distribution loaders, kernels, Windows), the Linux guest
+//! recovery mode and the firmware setup, and starts the chosen one itself: EFI images with
+//! LoadImage/StartImage and kernels through their EFI stub with `linuxboot`.
+//! No GRUB is involved.
+//!
+//! `\loader\loader.conf` on the boot volume may set `timeout` (seconds; 0
+//! boots the default at once) and `default` (start of an entry title, `*`
+//! allowed at the end).
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use uefi::runtime::{self, ResetType, VariableAttributes, VariableVendor};
+use uefi::{CString16, Handle, Status};
+
+use crate::osprober::{Boot, Target};
+use crate::{i18n, println, InputEvent};
+
+/// Countdown when loader.conf sets none; the old selector waited as long.
+const DEFAULT_TIMEOUT_SECS: u32 = 60;
+/// EFI_OS_INDICATIONS_BOOT_TO_FW_UI.
+const OS_INDICATIONS_BOOT_TO_FW_UI: u64 = 1;
+
+pub enum Action {
+    /// Keep booting the running Zenox.
+    Continue,
+    /// Another Zenox install.
+    Zenox(Handle, usize),
+    /// A system `osprober` found on a volume.
+    System(Handle, Target),
+    LinuxGuest,
+    /// This Zenox, in `crate::recovery` mode.
+    Recovery,
+    /// This Zenox with the `crate::safemode` driver set.
+    SafeMode,
+    FirmwareSetup,
+}
+
+pub struct Entry {
+    pub title: String,
+    pub action: Action,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoaderConf {
+    pub timeout: Option<u32>,
+    pub default: Option<String>,
+}
+
+/// The keys of `\loader\loader.conf` the menu honours.
+pub fn parse_loader_conf(text: &str) -> LoaderConf {
+    let mut conf = LoaderConf::default();
+    for line in text.lines() {
+        let line = line.trim();
+        let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match key {
+            "timeout" => conf.timeout = value.trim().parse().ok(),
+            "default" if !value.trim().is_empty() => conf.default = Some(String::from(value.trim())),
+            _ => {}
+        }
+    }
+    conf
+}
+
+/// First title that `pattern` starts, ignoring case.
+pub fn find_default(titles: &[&str], pattern: &str) -> Option<usize> {
+    let pattern = pattern.trim_end_matches('*').to_ascii_lowercase();
+    titles
+        .iter()
+        .position(|title| title.to_ascii_lowercase().starts_with(pattern.as_str()))
+}
+
+/// The EFI stub command line for a kernel: its initrds, then `options`.
+pub fn kernel_command_line(initrds: &[String], options: &str) -> String {
+    let mut line = String::new();
+    for initrd in initrds {
+        line.push_str("initrd=");
+        line.push_str(initrd.replace('/', "\\").as_str());
+        line.push(' ');
+    }
+    line.push_str(options.trim());
+    String::from(line.trim_end())
+}
+
+/// A path from a GRUB config without its `(device)` or `$root` prefix.
+fn strip_grub_device(path: &str) -> &str {
+    if let Some(rest) = path.strip_prefix('(') {
+        return rest.split_once(')').map(|(_, path)| path).unwrap_or(path);
+    }
+    path.strip_prefix("$root").unwrap_or(path)
+}
+
+/// The `linux` and `initrd` lines of a copied GRUB menuentry as a kernel to
+/// boot directly.
+pub fn linux_from_menu(body: &str) -> Option<Boot> {
+    let mut kernel = None;
+    let mut options = String::new();
+    let mut initrds = Vec::new();
+    for line in body.lines() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("linux" | "linuxefi") => {
+                kernel = words.next().map(|path| String::from(strip_grub_device(path)));
+                options = words.collect::<Vec<_>>().join(" ");
+            }
+            Some("initrd" | "initrdefi") => {
+                initrds = words.map(|path| String::from(strip_grub_device(path))).collect();
+            }
+            _ => {}
+        }
+    }
+    Some(Boot::Linux {
+        kernel: kernel?,
+        initrds,
+        options,
+    })
+}
+
+/// Selection and countdown of the menu, apart from the screen.
+pub struct Menu {
+    pub entries: Vec<Entry>,
+    pub selected: usize,
+    pub default: usize,
+    /// Seconds left; `None` once a key stopped the countdown.
+    pub remaining: Option<u32>,
+}
+
+impl Menu {
+    pub fn new(entries: Vec<Entry>, default: usize, timeout: u32) -> Self {
+        let default = default.min(entries.len().saturating_sub(1));
+        Self {
+            entries,
+            selected: default,
+            default,
+            remaining: Some(timeout),
+        }
+    }
+
+    /// Handle a key; returns the entry to boot once one is chosen.
+    pub fn key(&mut self, event: InputEvent) -> Option<usize> {
+        self.remaining = None;
+        let count = self.entries.len();
+        match event {
+            InputEvent::Enter => Some(self.selected),
+            InputEvent::Escape => Some(self.default),
+            InputEvent::Up => {
+                self.selected = (self.selected + count - 1) % count;
+                None
+            }
+            InputEvent::Down => {
+                self.selected = (self.selected + 1) % count;
+                None
+            }
+            InputEvent::Char(ch) => ch
+                .to_digit(10)
+                .map(|digit| digit as usize)
+                .filter(|digit| (1..=count).contains(digit))
+                .map(|digit| digit - 1),
+            InputEvent::Backspace => None,
+        }
+    }
+
+    /// A second passed; returns the default once the countdown runs out.
+    pub fn tick(&mut self) -> Option<usize> {
+        match self.remaining {
+            Some(0) => Some(self.default),
+            Some(left) => {
+                self.remaining = Some(left - 1);
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+fn read_u64_variable(name: &uefi::CStr16) -> Option<u64> {
+    let (data, _) = runtime::get_variable_boxed(name, &VariableVendor::GLOBAL_VARIABLE).ok()?;
+    Some(u64::from_le_bytes(data.get(..8)?.try_into().ok()?))
+}
+
+fn firmware_setup_supported() -> bool {
+    read_u64_variable(uefi::cstr16!("OsIndicationsSupported"))
+        .map(|bits| bits & OS_INDICATIONS_BOOT_TO_FW_UI != 0)
+        .unwrap_or(false)
+}
+
+fn reboot_to_firmware_setup() -> Result<(), String> {
+    let name = uefi::cstr16!("OsIndications");
+    let current = read_u64_variable(name).unwrap_or(0);
+    let attrs =
+        VariableAttributes::NON_VOLATILE | VariableAttributes::BOOTSERVICE_ACCESS | VariableAttributes::RUNTIME_ACCESS;
+    let value = current | OS_INDICATIONS_BOOT_TO_FW_UI;
+    runtime::set_variable(name, &VariableVendor::GLOBAL_VARIABLE, attrs, &value.to_le_bytes())
+        .map_err(|err| alloc::format!("OsIndications: {:?}", err.status()))?;
+    runtime::reset(ResetType::COLD, Status::SUCCESS, None)
+}
+
+/// Everything the menu offers, and the entry to preselect.
+fn collect(current: Option<Handle>, installed: &[Handle]) -> (Vec<Entry>, usize, Vec<(Handle, Target)>) {
+    let mut entries = Vec::new();
+    if installed.is_empty() {
+        entries.push(Entry {
+            title: i18n::tr("boot.current"),
+            action: Action::Continue,
+        });
+    }
+    for (index, handle) in installed.iter().copied().enumerate() {
+        let action = if Some(handle) == current {
+            Action::Continue
+        } else {
+            Action::Zenox(handle, index + 1)
+        };
+        entries.push(Entry {
+            title: crate::installed_redux_handle_description(handle, index + 1),
+            action,
+        });
+    }
+    let default = installed
+        .iter()
+        .position(|handle| Some(*handle) == current)
+        .unwrap_or(0);
+
+    let guest = crate::detect_linux_guest_boot_target(current, installed.first().copied());
+    if guest.is_some() {
+        entries.push(Entry {
+            title: i18n::tr("boot.linux_guest"),
+            action: Action::LinuxGuest,
+        });
+    }
+
+    let found = crate::osprober::probe_handles();
+    for (handle, target) in found.iter() {
+        // The guest entry already boots the images in \EFI\LINUX there.
+        let guest_image = target.image().to_ascii_lowercase().starts_with("/efi/linux/");
+        if Some(*handle) == guest && guest_image {
+            continue;
+        }
+        entries.push(Entry {
+            title: target.title.clone(),
+            action: Action::System(*handle, target.clone()),
+        });
+    }
+
+    entries.push(Entry {
+        title: i18n::tr("boot.recovery"),
+        action: Action::Recovery,
+    });
+    entries.push(Entry {
+        title: i18n::tr("boot.safe_mode"),
+        action: Action::SafeMode,
+    });
+    if firmware_setup_supported() {
+        entries.push(Entry {
+            title: i18n::tr("boot.firmware"),
+            action: Action::FirmwareSetup,
+        });
+    }
+    (entries, default, found)
+}
+
+fn draw(menu: &Menu) {
+    crate::clear_screen();
+    println(i18n::tr("boot.title").as_str());
+    println("");
+    for (index, entry) in menu.entries.iter().enumerate() {
+        let marker = if index == menu.selected { ">" } else { " " };
+        println(alloc::format!("{} {}) {}", marker, index + 1, entry.title).as_str());
+    }
+    println("");
+    println(i18n::tr("boot.hint").as_str());
+    if let Some(left) = menu.remaining {
+        println(i18n::trf("boot.countdown", &[&left]).as_str());
+    }
+}
+
+/// Start a system `osprober` found on `handle`.
+fn boot_target(handle: Handle, target: &Target) -> Result<(), String> {
+    let boot = match &target.boot {
+        Boot::Menu(body) => linux_from_menu(body).ok_or_else(|| String::from("la entrada no tiene linea linux"))?,
+        other => other.clone(),
+    };
+    let to_efi = |path: &str| {
+        CString16::try_from(path.replace('/', "\\").as_str()).map_err(|_| alloc::format!("ruta invalida: {}", path))
+    };
+    match boot {
+        Boot::Chain(path) => crate::start_image_at(handle, &to_efi(path.as_str())?, None),
+        Boot::Linux {
+            kernel,
+            initrds,
+            options,
+        } => crate::linuxboot::boot(handle, kernel.as_str(), initrds.as_slice(), options.as_str()),
+        Boot::Menu(_) => Err(String::from("la entrada no tiene linea linux")),
+    }
+}
+
+/// Show the menu and boot the choice. Returns to let this Zenox go on.
+pub fn run() {
+    let current = crate::current_boot_device_handle();
+    let installed = crate::find_installed_redux_handles(None);
+    let (entries, mut default, found) = collect(current, installed.as_slice());
+    crate::osprober::sync(found.as_slice());
+    let only_this = entries
+        .iter()
+        .all(|entry| matches!(entry.action, Action::Continue | Action::Recovery | Action::SafeMode));
+    if installed.is_empty() && only_this {
+        return;
+    }
+
+    let conf = current
+        .and_then(|handle| crate::read_file_from_fs_handle(handle, uefi::cstr16!("\\loader\\loader.conf")))
+        .map(|raw| parse_loader_conf(String::from_utf8_lossy(raw.as_slice()).as_ref()))
+        .unwrap_or_default();
+    if let Some(pattern) = conf.default.as_deref() {
+        let titles: Vec<&str> = entries.iter().map(|entry| entry.title.as_str()).collect();
+        default = find_default(titles.as_slice(), pattern).unwrap_or(default);
+    }
+
+    unsafe {
+        crate::QUIET_BOOT = false;
+    }
+    let mut menu = Menu::new(entries, default, conf.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS));
+    draw(&menu);
+    let mut ticks = 0u32;
+    let choice = loop {
+        if let Some(event) = crate::poll_input_event() {
+            if let Some(choice) = menu.key(event) {
+                break choice;
+            }
+            draw(&menu);
+        }
+        ticks += 1;
+        if ticks == 100 {
+            ticks = 0;
+            if let Some(choice) = menu.tick() {
+                break choice;
+            }
+            draw(&menu);
+        }
+        uefi::boot::stall(10_000);
+    };
+
+    let entry = &menu.entries[choice];
+    crate::clear_screen();
+    match &entry.action {
+        Action::Continue => {
+            println(i18n::trf("boot.booting_current", &[&(choice + 1)]).as_str());
+            uefi::boot::stall(350_000);
+            crate::clear_screen();
+            return;
+        }
+        Action::Recovery => {
+            println(i18n::tr("boot.booting_recovery").as_str());
+            crate::recovery::enter();
+            uefi::boot::stall(350_000);
+            crate::clear_screen();
+            return;
+        }
+        Action::SafeMode => {
+            println(i18n::tr("boot.booting_safe_mode").as_str());
+            crate::safemode::enter();
+            uefi::boot::stall(350_000);
+            crate::clear_screen();
+            return;
+        }
+        Action::Zenox(handle, ordinal) => {
+            println(i18n::trf("boot.booting_installed", &[&ordinal]).as_str());
+            match crate::launch_installed_redux(Some(*handle)) {
+                Ok(path) => println(i18n::trf("boot.returned", &[&path]).as_str()),
+                Err(err) => {
+                    println(i18n::trf("boot.failed_installed", &[&err]).as_str());
+                    uefi::boot::stall(2_500_000);
+                }
+            }
+        }
+        Action::LinuxGuest => {
+            println(i18n::tr("boot.booting_linux").as_str());
+            match crate::launch_linux_guest_boot(current, installed.first().copied()) {
+                Ok(path) => println(i18n::trf("boot.returned", &[&path]).as_str()),
+                Err(err) => {
+                    println(i18n::trf("boot.failed_linux", &[&err]).as_str());
+                    uefi::boot::stall(2_500_000);
+                }
+            }
+        }
+        Action::System(handle, target) => {
+            println(i18n::trf("boot.booting_entry", &[&entry.title]).as_str());
+            match boot_target(*handle, target) {
+                Ok(()) => println(i18n::trf("boot.returned", &[&target.image()]).as_str()),
+                Err(err) => {
+                    println(i18n::trf("boot.failed_entry", &[&entry.title, &err]).as_str());
+                    uefi::boot::stall(2_500_000);
+                }
+            }
+        }
+        Action::FirmwareSetup => {
+            if let Err(err) = reboot_to_firmware_setup() {
+                println(i18n::trf("boot.failed_entry", &[&entry.title, &err]).as_str());
+                uefi::boot::stall(2_500_000);
+            }
+        }
+    }
+    println(i18n::tr("boot.continuing").as_str());
+    uefi::boot::stall(400_000);
+    crate::clear_screen();
+}
+
+crate::selftest::kernel_tests! {
+    "bootmenu";
+
+    fn parses_loader_conf() {
+        let conf = parse_loader_conf("# loader\ntimeout 3\ndefault fedora*\nconsole-mode max\n");
+        crate::selftest::ensure_eq(conf.timeout, Some(3), "timeout")?;
+        crate::selftest::ensure_eq(conf.default.as_deref(), Some("fedora*"), "default")?;
+        let titles = ["Zenox OS 1 - INTERNO", "Fedora Linux (6.8)", "Windows Boot Manager"];
+        crate::selftest::ensure_eq(find_default(&titles, "fedora*"), Some(1), "patron")?;
+        crate::selftest::ensure_eq(find_default(&titles, "windows"), Some(2), "prefijo")?;
+        crate::selftest::ensure_eq(find_default(&titles, "arch"), None, "sin coincidencia")?;
+        crate::selftest::ensure_eq(parse_loader_conf("timeout menu-force\n").timeout, None, "timeout no numerico")
+    }
+
+    fn builds_kernel_command_lines() {
+        let initrds = [String::from("/intel-ucode.img"), String::from("/boot/initrd.img-6.1")];
+        crate::selftest::ensure_eq(
+            kernel_command_line(&initrds, "root=UUID=1 ro quiet"),
+            String::from("initrd=\\intel-ucode.img initrd=\\boot\\initrd.img-6.1 root=UUID=1 ro quiet"),
+            "linea",
+        )?;
+        crate::selftest::ensure_eq(kernel_command_line(&[], " ro "), String::from("ro"), "sin initrd")?;
+        let body = "search --no-floppy --fs-uuid --set=root abcd\nlinux ($root)/vmlinuz-6.1 root=UUID=abcd ro\n\
+                    initrd /intel-ucode.img (hd0,gpt2)/initrd.img-6.1\n";
+        crate::selftest::ensure_eq(
+            linux_from_menu(body),
+            Some(Boot::Linux {
+                kernel: String::from("/vmlinuz-6.1"),
+                initrds: alloc::vec![String::from("/intel-ucode.img"), String::from("/initrd.img-6.1")],
+                options: String::from("root=UUID=abcd ro"),
+            }),
+            "menuentry",
+        )?;
+        crate::selftest::ensure(linux_from_menu("chainloader /EFI/x.efi\n").is_none(), "sin linux")
+    }
+
+    fn navigates_and_times_out() {
+        let entry = |title: &str| Entry {
+            title: String::from(title),
+            action: Action::Continue,
+        };
+        let mut menu = Menu::new(alloc::vec![entry("a"), entry("b"), entry("c")], 1, 2);
+        crate::selftest::ensure_eq(menu.tick(), None, "primer segundo")?;
+        crate::selftest::ensure_eq(menu.tick(), None, "segundo segundo")?;
+        crate::selftest::ensure_eq(menu.tick(), Some(1), "fin de la cuenta")?;
+        crate::selftest::ensure_eq(menu.key(InputEvent::Down), None, "abajo")?;
+        crate::selftest::ensure_eq(menu.remaining, None, "tecla para la cuenta")?;
+        crate::selftest::ensure_eq(menu.key(InputEvent::Down), None, "vuelta")?;
+        crate::selftest::ensure_eq(menu.selected, 0, "seleccion")?;
+        crate::selftest::ensure_eq(menu.key(InputEvent::Up), None, "arriba")?;
+        crate::selftest::ensure_eq(menu.key(InputEvent::Enter), Some(2), "enter")?;
+        crate::selftest::ensure_eq(menu.key(InputEvent::Char('9')), None, "numero fuera de rango")?;
+        crate::selftest::ensure_eq(menu.key(InputEvent::Char('1')), Some(0), "numero")?;
+        crate::selftest::ensure_eq(menu.key(InputEvent::Escape), Some(1), "escape")
+    }
+}
@@ -0,0 +1,255 @@
+//! Boot profiling: how long each stage took on the way to the desktop, and
+//! the waits that used to be fixed stalls.
+//!
+//! Driver probes stay on the BSP because UEFI boot services are not safe to
+//! call from APs. What runs in parallel instead are the slow waits: a probe
+//! that would sit polling for a link or a ready bit registers a deferred
+//! check with `defer` and returns. Each check is an executor task that polls
+//! on the timer wheel until it completes or times out, so boot goes on to
+//! the next probe and the wait finishes under whichever loop (shell,
+//! desktop, runtime) is running tasks. Stage times and deferred completions
+//! go to the kernel log (`dmesg`) and to `boottime`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::spinlock::SpinLock;
+
+pub const MAX_STAGES: usize = 32;
+const MAX_DEFERRED: usize = 8;
+const DEFER_POLL_MS: u64 = 20;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StageTime {
+    pub name: &'static str,
+    /// Microseconds since the kernel started timing.
+    pub start_us: u64,
+    pub duration_us: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeferredState {
+    Pending,
+    Done(u64),
+    TimedOut,
+}
+
+#[derive(Clone, Copy)]
+struct Deferred {
+    name: &'static str,
+    since_us: u64,
+    timeout_us: u64,
+    ready: fn() -> bool,
+    state: DeferredState,
+}
+
+struct BootLog {
+    origin_us: u64,
+    stages: Vec<StageTime>,
+    deferred: Vec<Deferred>,
+    desktop_us: Option<u64>,
+}
+
+impl BootLog {
+    const fn new() -> Self {
+        Self {
+            origin_us: 0,
+            stages: Vec::new(),
+            deferred: Vec::new(),
+            desktop_us: None,
+        }
+    }
+
+    fn record(&mut self, name: &'static str, start_us: u64, end_us: u64) -> StageTime {
+        let stage = StageTime {
+            name,
+            start_us: start_us.saturating_sub(self.origin_us),
+            duration_us: end_us.saturating_sub(start_us),
+        };
+        if self.stages.len() < MAX_STAGES {
+            self.stages.push(stage);
+        }
+        stage
+    }
+
+    /// Poll deferred check `index` once and return its state.
+    fn check(&mut self, index: usize, now_us: u64) -> DeferredState {
+        let Some(d) = self.deferred.get_mut(index) else {
+            return DeferredState::TimedOut;
+        };
+        if d.state == DeferredState::Pending {
+            let elapsed = now_us.saturating_sub(d.since_us);
+            if (d.ready)() {
+                d.state = DeferredState::Done(elapsed);
+            } else if elapsed >= d.timeout_us {
+                d.state = DeferredState::TimedOut;
+            }
+        }
+        d.state
+    }
+}
+
+static BOOT: SpinLock<BootLog> = SpinLock::new(BootLog::new());
+
+/// Start the boot clock. Stage offsets are relative to this call.
+pub fn init() {
+    BOOT.lock().origin_us = crate::perf::now_us();
+}
+
+fn log(text: &str) {
+    crate::klog::log(crate::klog::Level::Info, text);
+}
+
+fn format_ms(us: u64) -> String {
+    alloc::format!("{}.{} ms", us / 1000, (us % 1000) / 100)
+}
+
+/// Run `f` as the boot stage `name`, timing it into the log and the trace.
+pub fn stage<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    let start = crate::perf::now_us();
+    let result = {
+        let _t = crate::trace::scope("boot", name);
+        f()
+    };
+    let stage = BOOT.lock().record(name, start, crate::perf::now_us());
+    log(alloc::format!("boot: {} {}", name, format_ms(stage.duration_us)).as_str());
+    result
+}
+
+/// Poll `probe` every `poll_us` until it yields a value or `timeout_us`
+/// passes. Replaces "retry after a fixed stall": a device that is ready
+/// early costs one poll interval, not the whole stall.
+pub fn wait_for<T>(timeout_us: u64, poll_us: u64, mut probe: impl FnMut() -> Option<T>) -> Option<T> {
+    let start = crate::perf::now_us();
+    loop {
+        if let Some(value) = probe() {
+            return Some(value);
+        }
+        if crate::perf::now_us().saturating_sub(start) >= timeout_us {
+            return None;
+        }
+        uefi::boot::stall(poll_us as usize);
+    }
+}
+
+/// `wait_for` for plain conditions.
+pub fn wait_until(timeout_us: u64, poll_us: u64, mut ready: impl FnMut() -> bool) -> bool {
+    wait_for(timeout_us, poll_us, || ready().then_some(())).is_some()
+}
+
+/// Finish a wait off the boot path: an executor task polls `ready` until it
+/// returns true or `timeout_us` passes.
+pub fn defer(name: &'static str, timeout_us: u64, ready: fn() -> bool) {
+    let index = {
+        let mut boot = BOOT.lock();
+        if boot.deferred.len() >= MAX_DEFERRED {
+            return;
+        }
+        boot.deferred.push(Deferred {
+            name,
+            since_us: crate::perf::now_us(),
+            timeout_us,
+            ready,
+            state: DeferredState::Pending,
+        });
+        boot.deferred.len() - 1
+    };
+    let wait = async move {
+        loop {
+            let state = BOOT.lock().check(index, crate::perf::now_us());
+            match state {
+                DeferredState::Pending => crate::executor::sleep_ms(DEFER_POLL_MS).await,
+                DeferredState::Done(us) => {
+                    crate::trace::instant("boot", "deferred_done", name);
+                    log(alloc::format!("boot: {} listo tras {} (diferido)", name, format_ms(us)).as_str());
+                    return;
+                }
+                DeferredState::TimedOut => {
+                    log(alloc::format!("boot: {} sin respuesta (diferido)", name).as_str());
+                    return;
+                }
+            }
+        }
+    };
+    if crate::executor::spawn("boot-wait", wait).is_none() {
+        BOOT.lock().deferred[index].state = DeferredState::TimedOut;
+        log(alloc::format!("boot: {} sin tarea libre para esperar", name).as_str());
+    }
+}
+
+/// The desktop is up: log the total and the slowest stages.
+pub fn desktop_ready() {
+    let (total, mut stages) = {
+        let mut boot = BOOT.lock();
+        let total = crate::perf::now_us().saturating_sub(boot.origin_us);
+        boot.desktop_us = Some(total);
+        (total, boot.stages.clone())
+    };
+    stages.sort_by(|a, b| b.duration_us.cmp(&a.duration_us));
+    let slowest: Vec<String> = stages
+        .iter()
+        .take(4)
+        .map(|s| alloc::format!("{} {}", s.name, format_ms(s.duration_us)))
+        .collect();
+    log(alloc::format!("boot: escritorio en {} ({})", format_ms(total), slowest.join(", ")).as_str());
+}
+
+pub fn stages() -> Vec<StageTime> {
+    BOOT.lock().stages.clone()
+}
+
+/// Shared implementation of `boottime`.
+pub fn command_lines(_args: &str) -> Vec<String> {
+    let boot = BOOT.lock();
+    let mut out = Vec::new();
+    match boot.desktop_us {
+        Some(us) => out.push(alloc::format!("Arranque hasta el escritorio: {}", format_ms(us))),
+        None => out.push(String::from("Arranque: el escritorio aun no se ha iniciado")),
+    }
+    for s in boot.stages.iter() {
+        out.push(alloc::format!(
+            "  {:<20} +{:>10}  {}",
+            s.name,
+            format_ms(s.start_us),
+            format_ms(s.duration_us)
+        ));
+    }
+    for d in boot.deferred.iter() {
+        let state = match d.state {
+            DeferredState::Pending => String::from("pendiente"),
+            DeferredState::Done(us) => alloc::format!("listo tras {}", format_ms(us)),
+            DeferredState::TimedOut => String::from("sin respuesta"),
+        };
+        out.push(alloc::format!("  {:<20} diferido: {}", d.name, state));
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "boottime";
+
+    fn stages_are_relative_to_origin() {
+        let mut boot = BootLog::new();
+        boot.origin_us = 1_000;
+        let s = boot.record("pci_scan", 1_500, 4_000);
+        crate::selftest::ensure_eq((s.start_us, s.duration_us), (500, 2_500), "etapa")?;
+        crate::selftest::ensure_eq(format_ms(2_560).as_str(), "2.5 ms", "formato")
+    }
+
+    fn deferred_checks_finish_or_time_out() {
+        fn never() -> bool {
+            false
+        }
+        fn always() -> bool {
+            true
+        }
+        let mut boot = BootLog::new();
+        for (name, ready) in [("lento", never as fn() -> bool), ("rapido", always as fn() -> bool)] {
+            boot.deferred.push(Deferred { name, since_us: 100, timeout_us: 1_000, ready, state: DeferredState::Pending });
+        }
+        crate::selftest::ensure_eq(boot.check(1, 400), DeferredState::Done(300), "listo")?;
+        crate::selftest::ensure_eq(boot.check(0, 800), DeferredState::Pending, "sigue pendiente")?;
+        crate::selftest::ensure_eq(boot.check(0, 1_100), DeferredState::TimedOut, "timeout")?;
+        crate::selftest::ensure_eq(boot.check(1, 5_000), DeferredState::Done(300), "sin repetir")
+    }
+}
@@ -0,0 +1,990 @@
+//! UEFI boot entries: list, reorder, enable, disable and delete them.
+//!
+//! Changes are staged in a `Plan` over the `State` read from the firmware.
+//! `Plan::changes` describes them without touching anything (the dry run),
+//! and `apply` first exports the previous state to `BACKUP_PATH` on the boot
+//! media, so `restore` can put it back. An apply that cannot write the
+//! backup changes nothing.
+//!
+//! Once the user has changed the order, the kernel stops moving Zenox OS to
+//! the front of BootOrder on every boot (`user_managed`); `bootvar auto`
+//! hands the order back to it.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use uefi::boot::{self, OpenProtocolAttributes, OpenProtocolParams};
+use uefi::fs::FileSystem as UefiFileSystem;
+use uefi::proto::device_path::DevicePath;
+use uefi::runtime::{VariableAttributes, VariableVendor};
+use uefi::{cstr16, CString16, Guid, Status};
+
+/// Restore file on the boot media, rewritten before every apply.
+pub const BACKUP_PATH: &str = "\\EFI\\ZENOX\\BOOTVAR.BAK";
+const BACKUP_HEADER: &str = "# Zenox OS: variables de arranque UEFI antes del ultimo cambio con bootvar";
+/// Vendor of the kernel's own UEFI variables.
+const ZENOX_VENDOR: Guid = uefi::guid!("5a3c1f0e-8b7d-4e2a-9c61-0d4b7e2f9a13");
+
+/// One Boot#### variable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub id: u16,
+    /// The EFI_LOAD_OPTION as stored.
+    pub raw: Vec<u8>,
+}
+
+impl Entry {
+    pub fn active(&self) -> bool {
+        self.raw.len() >= 4
+            && u32::from_le_bytes([self.raw[0], self.raw[1], self.raw[2], self.raw[3]]) & crate::UEFI_LOAD_OPTION_ACTIVE
+                != 0
+    }
+
+    pub fn description(&self) -> String {
+        crate::extract_boot_option_description(&self.raw).unwrap_or_default()
+    }
+
+    /// The device path, shortened to what identifies it.
+    pub fn path(&self) -> String {
+        crate::extract_boot_option_file_path(&self.raw)
+            .map(path_summary)
+            .unwrap_or_else(|| String::from("?"))
+    }
+
+    fn with_active(&self, active: bool) -> Vec<u8> {
+        let mut raw = self.raw.clone();
+        if raw.len() >= 4 {
+            let mut attributes = u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]);
+            if active {
+                attributes |= crate::UEFI_LOAD_OPTION_ACTIVE;
+            } else {
+                attributes &= !crate::UEFI_LOAD_OPTION_ACTIVE;
+            }
+            raw[..4].copy_from_slice(&attributes.to_le_bytes());
+        }
+        raw
+    }
+}
+
+/// Boot entries, BootOrder and BootNext as the firmware has them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct State {
+    pub order: Vec<u16>,
+    /// Every Boot#### variable, in BootOrder first.
+    pub entries: Vec<Entry>,
+    pub next: Option<u16>,
+    /// The entry this boot came from.
+    pub current: Option<u16>,
+}
+
+impl State {
+    pub fn entry(&self, id: u16) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+}
+
+fn read_u16_variable(name: &uefi::CStr16) -> Option<u16> {
+    let (raw, _) = uefi::runtime::get_variable_boxed(name, &VariableVendor::GLOBAL_VARIABLE).ok()?;
+    (raw.len() >= 2).then(|| u16::from_le_bytes([raw[0], raw[1]]))
+}
+
+pub fn read_state() -> Result<State, String> {
+    let order = crate::read_boot_order()?;
+    let mut ids = order.clone();
+    for key in uefi::runtime::variable_keys() {
+        let key = key.map_err(|err| alloc::format!("iterando variables UEFI: {:?}", err.status()))?;
+        if key.vendor != VariableVendor::GLOBAL_VARIABLE {
+            continue;
+        }
+        let name: String = String::from(&key.name);
+        if let Some(id) = crate::parse_boot_option_id_from_name(name.as_str()) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    let mut entries = Vec::new();
+    for id in ids {
+        if let Some(raw) = crate::read_boot_option_variable(id)? {
+            entries.push(Entry { id, raw });
+        }
+    }
+    Ok(State {
+        order,
+        entries,
+        next: read_u16_variable(cstr16!("BootNext")),
+        current: read_u16_variable(cstr16!("BootCurrent")),
+    })
+}
+
+/// Nodes of an EFI device path as (type, subtype, data), up to its end node.
+fn nodes(path: &[u8]) -> impl Iterator<Item = (u8, u8, &[u8])> {
+    let mut offset = 0usize;
+    core::iter::from_fn(move || {
+        if offset + 4 > path.len() {
+            return None;
+        }
+        let kind = path[offset];
+        let subtype = path[offset + 1];
+        let len = u16::from_le_bytes([path[offset + 2], path[offset + 3]]) as usize;
+        if kind == 0x7F || len < 4 || offset + len > path.len() {
+            return None;
+        }
+        let data = &path[offset + 4..offset + len];
+        offset += len;
+        Some((kind, subtype, data))
+    })
+}
+
+/// The file a media FilePath node names.
+fn file_of(path: &[u8]) -> String {
+    let mut file = String::new();
+    for (_, _, data) in nodes(path).filter(|(kind, subtype, _)| (*kind, *subtype) == (0x04, 0x04)) {
+        let units = data.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]]));
+        for ch in core::char::decode_utf16(units.take_while(|unit| *unit != 0)) {
+            file.push(ch.unwrap_or('?'));
+        }
+    }
+    file
+}
+
+/// A partition as a hard-drive node names it: number and signature (GPT
+/// partition GUID, or MBR disk id).
+pub type Partition = (u32, [u8; 16]);
+
+fn partition_of(path: &[u8]) -> Option<Partition> {
+    nodes(path).find_map(|(kind, subtype, data)| {
+        if (kind, subtype) != (0x04, 0x01) || data.len() < 38 {
+            return None;
+        }
+        let mut signature = [0u8; 16];
+        signature.copy_from_slice(&data[20..36]);
+        Some((u32::from_le_bytes([data[0], data[1], data[2], data[3]]), signature))
+    })
+}
+
+/// Short text for an EFI device path: partition and file when it has them,
+/// else the kind of device it names.
+pub fn path_summary(path: &[u8]) -> String {
+    let mut device = "";
+    for (kind, subtype, _) in nodes(path) {
+        match (kind, subtype) {
+            (0x03, 0x05) | (0x03, 0x0F) => device = "USB",
+            (0x03, 0x12) => device = "SATA",
+            (0x03, 0x17) => device = "NVMe",
+            (0x03, 0x0B) => device = "Red",
+            (0x03, 0x1F) | (0x03, 0x18) => device = "HTTP",
+            (0x04, 0x06) | (0x04, 0x07) => device = "Firmware",
+            (0x05, _) => device = "BBS (legacy)",
+            _ => {}
+        }
+    }
+    let mut out = String::new();
+    if let Some((number, _)) = partition_of(path) {
+        let _ = write!(out, "HD({}) ", number);
+    } else if !device.is_empty() {
+        out.push_str(device);
+        out.push(' ');
+    }
+    out.push_str(file_of(path).as_str());
+    let trimmed = out.trim_end();
+    if trimmed.is_empty() {
+        String::from("?")
+    } else {
+        String::from(trimmed)
+    }
+}
+
+/// Partitions some handle's device path names right now.
+pub fn present_partitions() -> Vec<Partition> {
+    let mut out = Vec::new();
+    for handle in boot::find_handles::<DevicePath>().unwrap_or_default() {
+        let params = OpenProtocolParams {
+            handle,
+            agent: boot::image_handle(),
+            controller: None,
+        };
+        let Ok(path) = (unsafe { boot::open_protocol::<DevicePath>(params, OpenProtocolAttributes::GetProtocol) })
+        else {
+            continue;
+        };
+        if let Some(partition) = partition_of(path.as_bytes()) {
+            if !out.contains(&partition) {
+                out.push(partition);
+            }
+        }
+    }
+    out
+}
+
+/// The partition a volume handle's device path names.
+pub fn handle_partition(handle: uefi::Handle) -> Option<Partition> {
+    let params = OpenProtocolParams {
+        handle,
+        agent: boot::image_handle(),
+        controller: None,
+    };
+    let path = unsafe { boot::open_protocol::<DevicePath>(params, OpenProtocolAttributes::GetProtocol) }.ok()?;
+    partition_of(path.as_bytes())
+}
+
+/// Entries that boot something on `partition`.
+pub fn entries_on(state: &State, partition: Partition) -> Vec<u16> {
+    state
+        .entries
+        .iter()
+        .filter(|entry| crate::extract_boot_option_file_path(&entry.raw).and_then(partition_of) == Some(partition))
+        .map(|entry| entry.id)
+        .collect()
+}
+
+/// Why `clean` would delete an entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stale {
+    /// Boots the same file with the same options as the entry kept.
+    Duplicate(u16),
+    /// Its partition is on no disk the firmware sees.
+    Missing,
+}
+
+/// Entries `clean` would delete. Of a set of duplicates it keeps this boot's
+/// entry, else the first in BootOrder. Only entries whose path names a
+/// partition can be missing, and none is while `present` is empty; network
+/// boot, firmware apps and whole removable devices are left alone.
+pub fn find_stale(state: &State, present: &[Partition]) -> Vec<(u16, Stale)> {
+    let rank = |id: u16| {
+        (
+            state.current != Some(id),
+            state.order.iter().position(|&x| x == id).unwrap_or(usize::MAX),
+            id,
+        )
+    };
+    let mut entries: Vec<&Entry> = state.entries.iter().collect();
+    entries.sort_by_key(|entry| rank(entry.id));
+
+    let mut kept: Vec<(Vec<u8>, u16)> = Vec::new();
+    let mut out = Vec::new();
+    for entry in entries {
+        let Some(path) = crate::extract_boot_option_file_path(&entry.raw) else {
+            continue;
+        };
+        let partition = partition_of(path);
+        let current = state.current == Some(entry.id);
+        if let Some(partition) = partition {
+            if !current && !present.is_empty() && !present.contains(&partition) {
+                out.push((entry.id, Stale::Missing));
+                continue;
+            }
+        }
+        // Short and full forms of a path to the same file compare equal.
+        let mut key = match partition {
+            Some((number, signature)) => {
+                let mut key = number.to_le_bytes().to_vec();
+                key.extend_from_slice(&signature);
+                key.extend_from_slice(file_of(path).to_ascii_lowercase().as_bytes());
+                key
+            }
+            None => path.to_vec(),
+        };
+        key.push(0);
+        key.extend_from_slice(crate::extract_boot_option_optional_data(&entry.raw).unwrap_or(&[]));
+        match kept.iter().find(|(other, _)| *other == key) {
+            Some((_, keeper)) if !current => out.push((entry.id, Stale::Duplicate(*keeper))),
+            _ => kept.push((key, entry.id)),
+        }
+    }
+    out.sort_by_key(|(id, _)| *id);
+    out
+}
+
+/// One line per entry `clean` would delete.
+pub fn stale_lines(state: &State, stale: &[(u16, Stale)]) -> Vec<String> {
+    stale
+        .iter()
+        .map(|(id, why)| {
+            let name = state.entry(*id).map(Entry::description).unwrap_or_default();
+            match why {
+                Stale::Duplicate(keeper) => alloc::format!("Boot{:04X} ({}): copia de Boot{:04X}", id, name, keeper),
+                Stale::Missing => alloc::format!("Boot{:04X} ({}): su particion ya no existe", id, name),
+            }
+        })
+        .collect()
+}
+
+/// A line for the boot log when `clean` has something to do.
+pub fn stale_hint() -> Option<String> {
+    let state = read_state().ok()?;
+    let stale = find_stale(&state, &present_partitions());
+    (!stale.is_empty()).then(|| {
+        alloc::format!(
+            "bootvar: {} entradas de arranque duplicadas u obsoletas; 'bootvar clean' las muestra",
+            stale.len()
+        )
+    })
+}
+
+/// "0003", "3" or "Boot0003".
+pub fn parse_id(text: &str) -> Option<u16> {
+    let text = text.trim();
+    let hex = text
+        .strip_prefix("Boot")
+        .or_else(|| text.strip_prefix("boot"))
+        .unwrap_or(text);
+    if hex.is_empty() || hex.len() > 4 {
+        return None;
+    }
+    u16::from_str_radix(hex, 16).ok()
+}
+
+fn id_list(ids: &[u16]) -> String {
+    let mut out = String::new();
+    for (index, id) in ids.iter().enumerate() {
+        if index > 0 {
+            out.push(',');
+        }
+        let _ = write!(out, "{:04X}", id);
+    }
+    out
+}
+
+/// What the boot variables should become.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Plan {
+    pub order: Vec<u16>,
+    /// Entries to leave inactive; the rest end up active.
+    pub inactive: Vec<u16>,
+    pub deleted: Vec<u16>,
+    pub next: Option<u16>,
+}
+
+impl Plan {
+    /// No changes over `state`.
+    pub fn new(state: &State) -> Self {
+        Self {
+            order: state.order.clone(),
+            inactive: state.entries.iter().filter(|e| !e.active()).map(|e| e.id).collect(),
+            deleted: Vec::new(),
+            next: state.next,
+        }
+    }
+
+    /// Move `id` by `delta` places in BootOrder, adding it at the end first
+    /// when it is not there.
+    pub fn shift(&mut self, id: u16, delta: i32) {
+        if !self.order.contains(&id) {
+            self.order.push(id);
+        }
+        let Some(from) = self.order.iter().position(|&x| x == id) else {
+            return;
+        };
+        let to = (from as i32 + delta).clamp(0, self.order.len() as i32 - 1) as usize;
+        let id = self.order.remove(from);
+        self.order.insert(to, id);
+    }
+
+    pub fn set_active(&mut self, id: u16, active: bool) {
+        self.inactive.retain(|&x| x != id);
+        if !active {
+            self.inactive.push(id);
+        }
+    }
+
+    pub fn delete(&mut self, id: u16) {
+        self.order.retain(|&x| x != id);
+        if !self.deleted.contains(&id) {
+            self.deleted.push(id);
+        }
+        if self.next == Some(id) {
+            self.next = None;
+        }
+    }
+
+    /// Delete everything `find_stale` found.
+    pub fn clean(&mut self, stale: &[(u16, Stale)]) {
+        for (id, _) in stale {
+            self.delete(*id);
+        }
+    }
+
+    pub fn is_active(&self, id: u16) -> bool {
+        !self.inactive.contains(&id)
+    }
+
+    /// Why the plan would leave the machine unable to boot, if it would.
+    pub fn check(&self, state: &State) -> Result<(), String> {
+        // BootOrder may already name entries that are gone; only new ones
+        // must exist.
+        let added = self.order.iter().filter(|id| !state.order.contains(id));
+        let next = self.next.iter().filter(|id| Some(**id) != state.next);
+        for id in added.chain(next) {
+            if state.entry(*id).is_none() || self.deleted.contains(id) {
+                return Err(alloc::format!("Boot{:04X} no existe", id));
+            }
+        }
+        if let Some(current) = state.current {
+            if self.deleted.contains(&current) {
+                return Err(alloc::format!(
+                    "Boot{:04X} es la entrada de este arranque; no se borra",
+                    current
+                ));
+            }
+        }
+        let mut seen = Vec::new();
+        for id in self.order.iter() {
+            if seen.contains(id) {
+                return Err(alloc::format!("Boot{:04X} repetida en BootOrder", id));
+            }
+            seen.push(*id);
+        }
+        if !state.order.is_empty()
+            && !self
+                .order
+                .iter()
+                .any(|id| self.is_active(*id) && state.entry(*id).is_some())
+        {
+            return Err(String::from("BootOrder quedaria sin ninguna entrada activa"));
+        }
+        Ok(())
+    }
+
+    /// One line per change over `state`; empty when there are none.
+    pub fn changes(&self, state: &State) -> Vec<String> {
+        let mut out = Vec::new();
+        for entry in state.entries.iter() {
+            if self.deleted.contains(&entry.id) {
+                out.push(alloc::format!("Borrar Boot{:04X} ({})", entry.id, entry.description()));
+            } else if entry.active() != self.is_active(entry.id) {
+                out.push(alloc::format!(
+                    "{} Boot{:04X} ({})",
+                    if self.is_active(entry.id) {
+                        "Activar"
+                    } else {
+                        "Desactivar"
+                    },
+                    entry.id,
+                    entry.description()
+                ));
+            }
+        }
+        if self.order != state.order {
+            out.push(alloc::format!(
+                "BootOrder: {} -> {}",
+                id_list(&state.order),
+                id_list(&self.order)
+            ));
+        }
+        if self.next != state.next {
+            out.push(match self.next {
+                Some(id) => alloc::format!("BootNext: Boot{:04X} en el proximo arranque", id),
+                None => String::from("BootNext: se quita"),
+            });
+        }
+        out
+    }
+}
+
+fn vendor_attributes() -> VariableAttributes {
+    VariableAttributes::NON_VOLATILE | VariableAttributes::BOOTSERVICE_ACCESS | VariableAttributes::RUNTIME_ACCESS
+}
+
+/// Whether the user took over BootOrder with `bootvar`.
+pub fn user_managed() -> bool {
+    uefi::runtime::get_variable_boxed(cstr16!("BootvarManual"), &VariableVendor(ZENOX_VENDOR)).is_ok()
+}
+
+fn set_user_managed(manual: bool) -> Result<(), String> {
+    let vendor = VariableVendor(ZENOX_VENDOR);
+    let result = if manual {
+        uefi::runtime::set_variable(cstr16!("BootvarManual"), &vendor, vendor_attributes(), &[1])
+    } else {
+        uefi::runtime::delete_variable(cstr16!("BootvarManual"), &vendor)
+    };
+    match result {
+        Ok(()) => Ok(()),
+        Err(err) if err.status() == Status::NOT_FOUND => Ok(()),
+        Err(err) => Err(alloc::format!("escribiendo BootvarManual: {:?}", err.status())),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(out, "{:02x}", byte);
+    }
+    out
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(text.get(at..at + 2)?, 16).ok())
+        .collect()
+}
+
+/// `state` as the text of a restore file.
+pub fn export_text(state: &State) -> String {
+    let mut out = String::from(BACKUP_HEADER);
+    out.push('\n');
+    let _ = writeln!(out, "BootOrder={}", id_list(&state.order));
+    if let Some(next) = state.next {
+        let _ = writeln!(out, "BootNext={:04X}", next);
+    }
+    for entry in state.entries.iter() {
+        let _ = writeln!(out, "Boot{:04X}={}", entry.id, hex(&entry.raw));
+    }
+    out
+}
+
+/// The state a restore file holds. `current` is not kept.
+pub fn parse_export(text: &str) -> Result<State, String> {
+    let mut state = State::default();
+    let mut has_order = false;
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let bad = || alloc::format!("linea {} no valida", number + 1);
+        let (key, value) = line.split_once('=').ok_or_else(bad)?;
+        match key {
+            "BootOrder" => {
+                has_order = true;
+                for id in value.split(',').filter(|id| !id.is_empty()) {
+                    state.order.push(parse_id(id).ok_or_else(bad)?);
+                }
+            }
+            "BootNext" => state.next = Some(parse_id(value).ok_or_else(bad)?),
+            _ => {
+                let id = crate::parse_boot_option_id_from_name(key).ok_or_else(bad)?;
+                let raw = unhex(value).filter(|raw| raw.len() >= 6).ok_or_else(bad)?;
+                state.entries.push(Entry { id, raw });
+            }
+        }
+    }
+    if !has_order {
+        return Err(String::from("falta BootOrder"));
+    }
+    Ok(state)
+}
+
+fn backup_path() -> CString16 {
+    CString16::try_from(BACKUP_PATH).expect("bootvar: backup path")
+}
+
+fn export(state: &State) -> Result<(), String> {
+    let fs_proto = boot::get_image_file_system(boot::image_handle())
+        .map_err(|_| String::from("sin sistema de archivos de arranque para la copia"))?;
+    let mut fs = UefiFileSystem::new(fs_proto);
+    let _ = fs.create_dir_all(cstr16!("\\EFI\\ZENOX"));
+    fs.write(backup_path().as_ref(), export_text(state).as_bytes())
+        .map_err(|_| alloc::format!("no se pudo escribir la copia {}", BACKUP_PATH))
+}
+
+fn delete_boot_variable(name: &str) -> Result<(), String> {
+    let name16 = CString16::try_from(name).map_err(|_| String::from("nombre de variable invalido"))?;
+    match uefi::runtime::delete_variable(name16.as_ref(), &VariableVendor::GLOBAL_VARIABLE) {
+        Ok(()) => Ok(()),
+        Err(err) if err.status() == Status::NOT_FOUND => Ok(()),
+        Err(err) => Err(alloc::format!("borrando {}: {:?}", name, err.status())),
+    }
+}
+
+/// Apply `plan` over `state` after saving `state` to the restore file.
+/// Returns the changes made.
+pub fn apply(state: &State, plan: &Plan) -> Result<Vec<String>, String> {
+    plan.check(state)?;
+    let mut changes = plan.changes(state);
+    if changes.is_empty() {
+        return Ok(changes);
+    }
+    export(state)?;
+    for entry in state.entries.iter() {
+        if plan.deleted.contains(&entry.id) {
+            delete_boot_variable(alloc::format!("Boot{:04X}", entry.id).as_str())?;
+        } else if entry.active() != plan.is_active(entry.id) {
+            crate::write_boot_option_variable(entry.id, &entry.with_active(plan.is_active(entry.id)))?;
+        }
+    }
+    if plan.order != state.order {
+        crate::write_boot_order(&plan.order)?;
+        set_user_managed(true)?;
+    }
+    if plan.next != state.next {
+        match plan.next {
+            Some(id) => crate::write_boot_next(id)?,
+            None => delete_boot_variable("BootNext")?,
+        }
+    }
+    changes.push(alloc::format!("Copia del estado anterior en {}", BACKUP_PATH));
+    Ok(changes)
+}
+
+fn read_backup() -> Result<State, String> {
+    let fs_proto = boot::get_image_file_system(boot::image_handle())
+        .map_err(|_| String::from("sin sistema de archivos de arranque"))?;
+    let mut fs = UefiFileSystem::new(fs_proto);
+    let raw = fs
+        .read(backup_path().as_ref())
+        .map_err(|_| alloc::format!("no hay copia en {}", BACKUP_PATH))?;
+    parse_export(core::str::from_utf8(&raw).map_err(|_| String::from("copia no es texto"))?)
+}
+
+/// Put back the variables saved by the last apply. With `dry_run`, only say
+/// what would change. Entries created since are left alone.
+pub fn restore(dry_run: bool) -> Result<Vec<String>, String> {
+    let saved = read_backup()?;
+    let now = read_state()?;
+    let mut lines = Vec::new();
+    for entry in saved.entries.iter() {
+        match now.entry(entry.id) {
+            Some(existing) if existing.raw == entry.raw => {}
+            Some(_) => lines.push(alloc::format!(
+                "Reescribir Boot{:04X} ({})",
+                entry.id,
+                entry.description()
+            )),
+            None => lines.push(alloc::format!("Recrear Boot{:04X} ({})", entry.id, entry.description())),
+        }
+    }
+    if saved.order != now.order {
+        lines.push(alloc::format!(
+            "BootOrder: {} -> {}",
+            id_list(&now.order),
+            id_list(&saved.order)
+        ));
+    }
+    if saved.next != now.next {
+        lines.push(match saved.next {
+            Some(id) => alloc::format!("BootNext: Boot{:04X}", id),
+            None => String::from("BootNext: se quita"),
+        });
+    }
+    if dry_run || lines.is_empty() {
+        return Ok(lines);
+    }
+    for entry in saved.entries.iter() {
+        if now
+            .entry(entry.id)
+            .map(|existing| existing.raw != entry.raw)
+            .unwrap_or(true)
+        {
+            crate::write_boot_option_variable(entry.id, &entry.raw)?;
+        }
+    }
+    if saved.order != now.order {
+        crate::write_boot_order(&saved.order)?;
+    }
+    if saved.next != now.next {
+        match saved.next {
+            Some(id) => crate::write_boot_next(id)?,
+            None => delete_boot_variable("BootNext")?,
+        }
+    }
+    Ok(lines)
+}
+
+/// Every entry as `plan` would leave it, in its new BootOrder place: id and
+/// the cells position, Boot####, state, name and path.
+pub fn rows(state: &State, plan: &Plan) -> Vec<(u16, Vec<String>)> {
+    let position = |id: u16| plan.order.iter().position(|&x| x == id);
+    let mut entries: Vec<&Entry> = state.entries.iter().collect();
+    entries.sort_by_key(|entry| (position(entry.id).unwrap_or(usize::MAX), entry.id));
+    entries
+        .into_iter()
+        .map(|entry| {
+            let mut flags = String::from(if plan.deleted.contains(&entry.id) {
+                "borrar"
+            } else if plan.is_active(entry.id) {
+                "activa"
+            } else {
+                "inactiva"
+            });
+            if state.current == Some(entry.id) {
+                flags.push_str(", actual");
+            }
+            if plan.next == Some(entry.id) {
+                flags.push_str(", BootNext");
+            }
+            let cells = alloc::vec![
+                position(entry.id)
+                    .map(|p| alloc::format!("{}", p + 1))
+                    .unwrap_or_else(|| String::from("-")),
+                alloc::format!("Boot{:04X}", entry.id),
+                flags,
+                entry.description(),
+                entry.path(),
+            ];
+            (entry.id, cells)
+        })
+        .collect()
+}
+
+/// One line per entry: position in BootOrder, id, state, name and path.
+pub fn entry_lines(state: &State, plan: &Plan) -> Vec<String> {
+    rows(state, plan)
+        .into_iter()
+        .map(|(_, cells)| {
+            alloc::format!(
+                "{:>2} {} [{}] {} - {}",
+                cells[0],
+                cells[1],
+                cells[2],
+                cells[3],
+                cells[4]
+            )
+        })
+        .collect()
+}
+
+fn usage() -> Vec<String> {
+    alloc::vec![
+        String::from("Uso: bootvar [list]"),
+        String::from("     bootvar order <id,id,...> | first <id> [-n]"),
+        String::from("     bootvar enable|disable|delete|next <id> [-n]"),
+        String::from("     bootvar clean [-n] [--yes]"),
+        String::from("     bootvar restore [-n] | auto | gui"),
+        String::from("  -n (o --dry-run) muestra los cambios sin aplicarlos."),
+    ]
+}
+
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut words: Vec<&str> = args.split_whitespace().collect();
+    let before = words.len();
+    words.retain(|word| *word != "-n" && *word != "--dry-run");
+    let dry_run = words.len() != before;
+    let before = words.len();
+    words.retain(|word| *word != "-y" && *word != "--yes");
+    let confirmed = words.len() != before;
+    let verb = words.first().copied().unwrap_or("list");
+    let target = words.get(1).copied();
+
+    match verb {
+        "restore" => {
+            return match restore(dry_run) {
+                Ok(lines) if lines.is_empty() => alloc::vec![String::from("bootvar: nada que restaurar")],
+                Ok(mut lines) => {
+                    if dry_run {
+                        lines.insert(0, String::from("bootvar (simulacion):"));
+                    } else {
+                        lines.insert(0, alloc::format!("bootvar: restaurado desde {}", BACKUP_PATH));
+                    }
+                    lines
+                }
+                Err(err) => alloc::vec![alloc::format!("bootvar: {}", err)],
+            };
+        }
+        "auto" => {
+            return match set_user_managed(false) {
+                Ok(()) => alloc::vec![String::from(
+                    "bootvar: el kernel vuelve a poner Zenox OS primero en BootOrder al arrancar"
+                )],
+                Err(err) => alloc::vec![alloc::format!("bootvar: {}", err)],
+            };
+        }
+        _ => {}
+    }
+
+    let state = match read_state() {
+        Ok(state) => state,
+        Err(err) => return alloc::vec![alloc::format!("bootvar: {}", err)],
+    };
+    let mut plan = Plan::new(&state);
+    let id = target.and_then(parse_id);
+    if verb == "clean" {
+        let stale = find_stale(&state, &present_partitions());
+        if stale.is_empty() {
+            return alloc::vec![String::from("bootvar: no hay entradas duplicadas ni obsoletas")];
+        }
+        if !confirmed || dry_run {
+            let mut lines = alloc::vec![String::from("bootvar clean borraria:")];
+            lines.extend(
+                stale_lines(&state, &stale)
+                    .into_iter()
+                    .map(|line| alloc::format!("  {}", line)),
+            );
+            if !dry_run {
+                lines.push(String::from("Repite con 'bootvar clean --yes' para borrarlas."));
+            }
+            return lines;
+        }
+        plan.clean(&stale);
+    }
+    match (verb, id) {
+        ("clean", _) => {}
+        ("list", _) => {
+            let mut lines = entry_lines(&state, &plan);
+            if lines.is_empty() {
+                lines.push(String::from("bootvar: no hay entradas Boot####"));
+            }
+            if user_managed() {
+                lines.push(String::from(
+                    "BootOrder lo gestiona el usuario ('bootvar auto' lo devuelve al kernel)",
+                ));
+            }
+            return lines;
+        }
+        ("order", _) => {
+            let Some(list) = target else {
+                return usage();
+            };
+            let mut order = Vec::new();
+            for part in list.split(',') {
+                match parse_id(part) {
+                    Some(id) => order.push(id),
+                    None => return alloc::vec![alloc::format!("bootvar: id no valido: {}", part)],
+                }
+            }
+            plan.order = order;
+        }
+        ("first", Some(id)) => plan.shift(id, -(plan.order.len() as i32)),
+        ("enable", Some(id)) => plan.set_active(id, true),
+        ("disable", Some(id)) => plan.set_active(id, false),
+        ("delete", Some(id)) => plan.delete(id),
+        ("next", Some(id)) => plan.next = Some(id),
+        _ => return usage(),
+    }
+    if let Some(id) = id {
+        if state.entry(id).is_none() {
+            return alloc::vec![alloc::format!("bootvar: Boot{:04X} no existe", id)];
+        }
+    }
+
+    if dry_run {
+        let mut lines = alloc::vec![String::from("bootvar (simulacion):")];
+        match plan.check(&state) {
+            Ok(()) => {
+                let changes = plan.changes(&state);
+                if changes.is_empty() {
+                    lines.push(String::from("  sin cambios"));
+                }
+                lines.extend(changes.into_iter().map(|line| alloc::format!("  {}", line)));
+            }
+            Err(err) => lines.push(alloc::format!("  rechazado: {}", err)),
+        }
+        return lines;
+    }
+    match apply(&state, &plan) {
+        Ok(lines) if lines.is_empty() => alloc::vec![String::from("bootvar: sin cambios")],
+        Ok(lines) => lines
+            .into_iter()
+            .map(|line| alloc::format!("bootvar: {}", line))
+            .collect(),
+        Err(err) => alloc::vec![alloc::format!("bootvar: {}", err)],
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "bootvar";
+
+    fn parses_ids() {
+        crate::selftest::ensure_eq(parse_id("0003"), Some(3), "hex")?;
+        crate::selftest::ensure_eq(parse_id("Boot001A"), Some(0x1A), "prefijo Boot")?;
+        crate::selftest::ensure_eq(parse_id("a"), Some(10), "corto")?;
+        crate::selftest::ensure_eq(parse_id("12345"), None, "largo")?;
+        crate::selftest::ensure_eq(parse_id("xyz"), None, "no hex")
+    }
+
+    fn plan_edits_and_checks() {
+        let state = sample_state();
+        let mut plan = Plan::new(&state);
+        crate::selftest::ensure(plan.changes(&state).is_empty(), "plan nuevo con cambios")?;
+        plan.shift(2, -5);
+        crate::selftest::ensure_eq(plan.order.clone(), alloc::vec![2, 1], "subir al principio")?;
+        plan.set_active(1, false);
+        crate::selftest::ensure_eq(plan.changes(&state).len(), 2, "desactivar y reordenar")?;
+        plan.delete(1);
+        crate::selftest::ensure_eq(plan.order.clone(), alloc::vec![2], "borrada sale de BootOrder")?;
+        crate::selftest::ensure(plan.check(&state).is_err(), "borrar la entrada actual")?;
+        let mut plan = Plan::new(&state);
+        plan.set_active(1, false);
+        plan.set_active(2, false);
+        crate::selftest::ensure(plan.check(&state).is_err(), "sin entradas activas")?;
+        let mut plan = Plan::new(&state);
+        plan.order.push(9);
+        crate::selftest::ensure(plan.check(&state).is_err(), "entrada inexistente")
+    }
+
+    fn export_round_trip() {
+        let mut state = sample_state();
+        state.current = None;
+        let text = export_text(&state);
+        crate::selftest::ensure_eq(parse_export(text.as_str()), Ok(state), "copia")?;
+        crate::selftest::ensure(parse_export("Boot0001=00").is_err(), "sin BootOrder")
+    }
+
+    fn finds_duplicates_and_missing() {
+        let mut state = sample_state();
+        let copy = sample_option("Zenox OS", "\\efi\\boot\\bootx64.efi");
+        state.entries.push(Entry { id: 3, raw: copy });
+        let mut gone = sample_option("ReduxOS", "\\EFI\\BOOT\\BOOTX64.EFI");
+        gone[6 + 2 * 8 + 4 + 20] ^= 0xFF;
+        state.entries.push(Entry { id: 4, raw: gone });
+        let present = [(2u32, [0x11u8; 16])];
+        let stale = find_stale(&state, &present);
+        crate::selftest::ensure_eq(
+            stale.clone(),
+            alloc::vec![(3, Stale::Duplicate(1)), (4, Stale::Missing)],
+            "obsoletas",
+        )?;
+        crate::selftest::ensure(find_stale(&state, &[]).iter().all(|(_, why)| *why != Stale::Missing), "sin discos")?;
+        state.current = Some(3);
+        crate::selftest::ensure_eq(
+            find_stale(&state, &present)[0],
+            (1, Stale::Duplicate(3)),
+            "se conserva la actual",
+        )?;
+        let mut plan = Plan::new(&state);
+        plan.clean(&stale);
+        crate::selftest::ensure_eq(plan.deleted.clone(), alloc::vec![3, 4], "plan de limpieza")
+    }
+
+    fn summarizes_paths() {
+        let raw = sample_option("Zenox OS", "\\EFI\\BOOT\\BOOTX64.EFI");
+        let entry = Entry { id: 1, raw };
+        crate::selftest::ensure_eq(entry.path(), String::from("HD(2) \\EFI\\BOOT\\BOOTX64.EFI"), "ruta")?;
+        crate::selftest::ensure_eq(entry.description(), String::from("Zenox OS"), "nombre")?;
+        crate::selftest::ensure(entry.active(), "activa")?;
+        let off = Entry { id: 1, raw: entry.with_active(false) };
+        crate::selftest::ensure(!off.active(), "desactivada")
+    }
+}
+
+fn sample_option(description: &str, file: &str) -> Vec<u8> {
+    let mut path = Vec::new();
+    let mut hd = alloc::vec![0x04, 0x01, 42, 0];
+    hd.extend_from_slice(&2u32.to_le_bytes());
+    hd.resize(24, 0);
+    hd.extend_from_slice(&[0x11; 16]);
+    hd.extend_from_slice(&[0x02, 0x02]);
+    path.extend_from_slice(&hd);
+    let units: Vec<u16> = file.encode_utf16().chain(core::iter::once(0)).collect();
+    let len = 4 + units.len() * 2;
+    path.extend_from_slice(&[0x04, 0x04, len as u8, (len >> 8) as u8]);
+    for unit in units {
+        path.extend_from_slice(&unit.to_le_bytes());
+    }
+    path.extend_from_slice(&[0x7F, 0xFF, 4, 0]);
+    crate::build_boot_load_option(description, &path, &[]).unwrap_or_default()
+}
+
+fn sample_state() -> State {
+    State {
+        order: alloc::vec![1, 2],
+        entries: alloc::vec![
+            Entry {
+                id: 1,
+                raw: sample_option("Zenox OS", "\\EFI\\BOOT\\BOOTX64.EFI"),
+            },
+            Entry {
+                id: 2,
+                raw: sample_option("Windows Boot Manager", "\\EFI\\Microsoft\\Boot\\bootmgfw.efi"),
+            },
+        ],
+        next: None,
+        current: Some(1),
+    }
+}
@@ -0,0 +1,446 @@
+//! Calculator: arithmetic expressions for the `calc` command and the keypad
+//! state behind the Calculadora window.
+//!
+//! Expressions take `+ - * / % ^`, parentheses, `sqrt(..)`, `abs(..)` and
+//! `pi`. `^` binds tighter than unary minus on its left (`-2^2` is -4) and
+//! groups to the right; exponents must be whole numbers. A decimal comma is
+//! read as a point, and `x` / `:` are accepted for `*` / `/`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Entries kept in the window's history list.
+pub const HISTORY_MAX: usize = 32;
+const MAX_DEPTH: usize = 64;
+const MAX_EXPONENT: i64 = 1024;
+const EXPR_MAX_BYTES: usize = 128;
+
+/// Keypad of the Calculadora window, row by row.
+pub const KEYPAD: [&str; 24] = [
+    "C", "<-", "(", ")", "sqrt", "pi", "^", "/", "7", "8", "9", "*", "4", "5", "6", "-", "1", "2", "3", "+", "0", ".",
+    "%", "=",
+];
+pub const KEYPAD_COLUMNS: usize = 4;
+
+const PI: f64 = core::f64::consts::PI;
+
+struct Parser<'a> {
+    src: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn skip_spaces(&mut self) {
+        while self.src.get(self.pos).is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_spaces();
+        self.src.get(self.pos).map(|b| match b {
+            b'x' | b'X' if !self.word_follows() => b'*',
+            b':' => b'/',
+            _ => *b,
+        })
+    }
+
+    /// An `x` followed by a letter belongs to a name, not a product.
+    fn word_follows(&self) -> bool {
+        self.src.get(self.pos + 1).is_some_and(|b| b.is_ascii_alphabetic())
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<f64, &'static str> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err("expresion demasiado anidada");
+        }
+        let mut value = self.term()?;
+        loop {
+            if self.eat(b'+') {
+                value += self.term()?;
+            } else if self.eat(b'-') {
+                value -= self.term()?;
+            } else {
+                break;
+            }
+        }
+        self.depth -= 1;
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<f64, &'static str> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat(b'*') {
+                value *= self.unary()?;
+            } else if self.eat(b'/') {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err("division por cero");
+                }
+                value /= divisor;
+            } else if self.eat(b'%') {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err("division por cero");
+                }
+                value = rem(value, divisor);
+            } else {
+                break;
+            }
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<f64, &'static str> {
+        if self.eat(b'-') {
+            return Ok(-self.unary()?);
+        }
+        if self.eat(b'+') {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<f64, &'static str> {
+        let base = self.primary()?;
+        if !self.eat(b'^') {
+            return Ok(base);
+        }
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err("expresion demasiado anidada");
+        }
+        let exponent = self.unary()?;
+        self.depth -= 1;
+        powi(base, exponent)
+    }
+
+    fn primary(&mut self) -> Result<f64, &'static str> {
+        match self.peek() {
+            Some(b'(') => {
+                self.pos += 1;
+                let value = self.expr()?;
+                if !self.eat(b')') {
+                    return Err("falta ')'");
+                }
+                Ok(value)
+            }
+            Some(b) if b.is_ascii_digit() || b == b'.' || b == b',' => self.number(),
+            Some(b) if b.is_ascii_alphabetic() => {
+                let start = self.pos;
+                while self.src.get(self.pos).is_some_and(|b| b.is_ascii_alphabetic()) {
+                    self.pos += 1;
+                }
+                let name = core::str::from_utf8(&self.src[start..self.pos]).unwrap_or("");
+                match name.to_ascii_lowercase().as_str() {
+                    "pi" => Ok(PI),
+                    "sqrt" | "raiz" => {
+                        let value = self.primary()?;
+                        if value < 0.0 {
+                            return Err("raiz de un negativo");
+                        }
+                        Ok(sqrt(value))
+                    }
+                    "abs" => Ok(self.primary()?.abs()),
+                    _ => Err("nombre desconocido"),
+                }
+            }
+            Some(_) => Err("simbolo inesperado"),
+            None => Err("expresion incompleta"),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, &'static str> {
+        let mut value = 0.0f64;
+        let mut scale = 0.0f64;
+        let mut digits = 0;
+        while let Some(&b) = self.src.get(self.pos) {
+            match b {
+                b'0'..=b'9' => {
+                    let digit = (b - b'0') as f64;
+                    if scale == 0.0 {
+                        value = value * 10.0 + digit;
+                    } else {
+                        value += digit * scale;
+                        scale /= 10.0;
+                    }
+                    digits += 1;
+                }
+                b'.' | b',' if scale == 0.0 => scale = 0.1,
+                b'.' | b',' => return Err("numero con dos decimales"),
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        if digits == 0 {
+            return Err("numero invalido");
+        }
+        Ok(value)
+    }
+}
+
+fn powi(base: f64, exponent: f64) -> Result<f64, &'static str> {
+    if exponent != (exponent as i64) as f64 {
+        return Err("exponente no entero");
+    }
+    let n = exponent as i64;
+    if n.abs() > MAX_EXPONENT {
+        return Err("exponente demasiado grande");
+    }
+    if n < 0 && base == 0.0 {
+        return Err("division por cero");
+    }
+    let mut result = 1.0;
+    let mut factor = base;
+    let mut k = n.unsigned_abs();
+    while k > 0 {
+        if k & 1 == 1 {
+            result *= factor;
+        }
+        factor *= factor;
+        k >>= 1;
+    }
+    Ok(if n < 0 { 1.0 / result } else { result })
+}
+
+/// Remainder with the sign of the dividend, like `%` on integers.
+fn rem(value: f64, divisor: f64) -> f64 {
+    let quotient = value / divisor;
+    let whole = if quotient.abs() < 9.0e15 {
+        (quotient as i64) as f64
+    } else {
+        quotient
+    };
+    value - divisor * whole
+}
+
+fn sqrt(value: f64) -> f64 {
+    if value == 0.0 {
+        return 0.0;
+    }
+    let mut x = if value > 1.0 { value / 2.0 } else { 1.0 };
+    for _ in 0..64 {
+        let next = 0.5 * (x + value / x);
+        if next == x {
+            break;
+        }
+        x = next;
+    }
+    x
+}
+
+/// Evaluate `expr`.
+pub fn eval(expr: &str) -> Result<f64, &'static str> {
+    if expr.len() > EXPR_MAX_BYTES {
+        return Err("expresion demasiado larga");
+    }
+    let mut parser = Parser {
+        src: expr.as_bytes(),
+        pos: 0,
+        depth: 0,
+    };
+    let value = parser.expr()?;
+    if parser.peek().is_some() {
+        return Err("simbolo inesperado");
+    }
+    if !value.is_finite() {
+        return Err("resultado fuera de rango");
+    }
+    Ok(value)
+}
+
+/// Up to ten decimals without trailing zeros; exponent form for huge values.
+pub fn format_number(value: f64) -> String {
+    if value.abs() >= 1e15 {
+        return alloc::format!("{:e}", value);
+    }
+    let mut text = alloc::format!("{:.10}", value);
+    while text.ends_with('0') {
+        text.pop();
+    }
+    if text.ends_with('.') {
+        text.pop();
+    }
+    if text == "-0" {
+        text = String::from("0");
+    }
+    text
+}
+
+/// Keypad and keyboard state of a Calculadora window.
+pub struct Calculator {
+    pub expr: String,
+    /// Last result or error, shown under the expression.
+    pub display: String,
+    /// "expr = result" lines, newest last.
+    pub history: Vec<String>,
+    /// The expression holds a result: a digit starts a new one, an
+    /// operator continues from it.
+    showing_result: bool,
+}
+
+impl Default for Calculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Calculator {
+    pub const fn new() -> Self {
+        Self {
+            expr: String::new(),
+            display: String::new(),
+            history: Vec::new(),
+            showing_result: false,
+        }
+    }
+
+    /// A typed key: digits, operators and parentheses, `=` or Enter,
+    /// Backspace, and `c` or Escape to clear. Returns whether it was used.
+    pub fn input(&mut self, key: char) -> bool {
+        match key {
+            '=' | '\n' | '\r' => self.evaluate(),
+            '\x08' => {
+                self.showing_result = false;
+                self.expr.pop().is_some()
+            }
+            'c' | 'C' | '\x1b' => {
+                self.expr.clear();
+                self.display.clear();
+                self.showing_result = false;
+                true
+            }
+            '0'..='9' | '.' | ',' | '(' => {
+                if self.showing_result {
+                    self.expr.clear();
+                    self.showing_result = false;
+                }
+                self.push(key)
+            }
+            '+' | '-' | '*' | '/' | '%' | '^' | ')' => {
+                self.showing_result = false;
+                self.push(key)
+            }
+            'x' | 'X' => self.input('*'),
+            ':' => self.input('/'),
+            _ => false,
+        }
+    }
+
+    /// A keypad button from `KEYPAD`.
+    pub fn press(&mut self, label: &str) -> bool {
+        match label {
+            "C" => self.input('c'),
+            "<-" => self.input('\x08'),
+            "sqrt" | "pi" => {
+                if self.showing_result {
+                    self.expr.clear();
+                    self.showing_result = false;
+                }
+                let text = if label == "pi" { "pi" } else { "sqrt(" };
+                if self.expr.len() + text.len() > EXPR_MAX_BYTES {
+                    return false;
+                }
+                self.expr.push_str(text);
+                true
+            }
+            _ => label.chars().next().is_some_and(|key| self.input(key)),
+        }
+    }
+
+    fn push(&mut self, key: char) -> bool {
+        if self.expr.len() >= EXPR_MAX_BYTES {
+            return false;
+        }
+        self.expr.push(key);
+        true
+    }
+
+    fn evaluate(&mut self) -> bool {
+        if self.expr.trim().is_empty() {
+            return false;
+        }
+        match eval(self.expr.as_str()) {
+            Ok(value) => {
+                let result = format_number(value);
+                if self.history.len() >= HISTORY_MAX {
+                    self.history.remove(0);
+                }
+                self.history.push(alloc::format!("{} = {}", self.expr.trim(), result));
+                self.display = result.clone();
+                self.expr = result;
+                self.showing_result = true;
+            }
+            Err(err) => self.display = alloc::format!("Error: {}", err),
+        }
+        true
+    }
+}
+
+pub fn command_lines(args: &str) -> Vec<String> {
+    let expr = args.trim();
+    if expr.is_empty() {
+        return alloc::vec![String::from(USAGE)];
+    }
+    match eval(expr) {
+        Ok(value) => alloc::vec![format_number(value)],
+        Err(err) => alloc::vec![alloc::format!("calc: {}", err)],
+    }
+}
+
+const USAGE: &str = "uso: calc <expresion>   (+ - * / % ^, parentesis, sqrt(x), abs(x), pi)";
+
+crate::selftest::kernel_tests! {
+    "calc";
+
+    fn precedence_and_errors() {
+        crate::selftest::ensure_eq(eval("2+3*4")?, 14.0, "precedencia")?;
+        crate::selftest::ensure_eq(eval("(2+3)*4")?, 20.0, "parentesis")?;
+        crate::selftest::ensure_eq(eval("-2^2")?, -4.0, "potencia antes que el signo")?;
+        crate::selftest::ensure_eq(eval("2^3^2")?, 512.0, "potencia a la derecha")?;
+        crate::selftest::ensure_eq(eval("2^-1")?, 0.5, "exponente negativo")?;
+        crate::selftest::ensure_eq(eval("7 % 3 + sqrt(16)")?, 5.0, "resto y raiz")?;
+        crate::selftest::ensure_eq(eval("1,5 x 2")?, 3.0, "coma decimal y x")?;
+        crate::selftest::ensure_eq(eval("1/0"), Err("division por cero"), "division por cero")?;
+        crate::selftest::ensure_eq(eval("2^0.5"), Err("exponente no entero"), "exponente")?;
+        crate::selftest::ensure_eq(eval("(1+2"), Err("falta ')'"), "parentesis sin cerrar")?;
+        crate::selftest::ensure_eq(eval("3 4"), Err("simbolo inesperado"), "sobra")
+    }
+
+    fn formatting_and_keypad() {
+        crate::selftest::ensure_eq(format_number(0.1 + 0.2).as_str(), "0.3", "redondeo")?;
+        crate::selftest::ensure_eq(format_number(-0.0).as_str(), "0", "menos cero")?;
+        crate::selftest::ensure_eq(format_number(1.0 / 3.0).as_str(), "0.3333333333", "periodico")?;
+
+        let mut calc = Calculator::new();
+        for key in ["1", "2", "+", "3", "="] {
+            calc.press(key);
+        }
+        crate::selftest::ensure_eq(calc.display.as_str(), "15", "suma")?;
+        calc.input('*');
+        calc.input('2');
+        calc.input('\n');
+        crate::selftest::ensure_eq(calc.display.as_str(), "30", "sigue desde el resultado")?;
+        calc.input('7');
+        crate::selftest::ensure_eq(calc.expr.as_str(), "7", "un digito empieza de nuevo")?;
+        calc.press("<-");
+        calc.press("sqrt");
+        calc.input('9');
+        calc.input(')');
+        calc.input('=');
+        crate::selftest::ensure_eq(calc.display.as_str(), "3", "raiz")?;
+        crate::selftest::ensure_eq(calc.history.len(), 3, "historial")
+    }
+}
@@ -0,0 +1,542 @@
+//! Month calendar and local events for the `cal` command and the
+//! Calendario window.
+//!
+//! "Today" comes from the wall clock (`timer`), which boot sets from the
+//! UEFI RTC and SNTP corrects later, shifted by the local time zone.
+//! Events live in `\REDUXOS\EVENTS.TXT`, one per line:
+//!
+//! ```text
+//! 2026-10-15 09:30 Dentista
+//! 2026-12-24 -- Nochebuena
+//! ```
+//!
+//! Events with a time also show up as notifications when that minute comes
+//! (see `alarm::service`).
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::spinlock::SpinLock;
+
+const DIR: &str = "REDUXOS";
+const EVENTS_FILE: &str = "EVENTS.TXT";
+const MAX_EVENTS: usize = 512;
+const TEXT_MAX_BYTES: usize = 80;
+/// Marker for events without a time in `EVENTS.TXT`.
+const ALL_DAY: &str = "--";
+
+pub const MONTH_NAMES: [&str; 12] = [
+    "Enero",
+    "Febrero",
+    "Marzo",
+    "Abril",
+    "Mayo",
+    "Junio",
+    "Julio",
+    "Agosto",
+    "Septiembre",
+    "Octubre",
+    "Noviembre",
+    "Diciembre",
+];
+/// Weeks start on Monday.
+pub const WEEKDAY_NAMES: [&str; 7] = ["Lu", "Ma", "Mi", "Ju", "Vi", "Sa", "Do"];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Date {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    pub fn new(year: i32, month: u8, day: u8) -> Option<Self> {
+        ((1..=12).contains(&month) && day >= 1 && day <= days_in_month(year, month)).then_some(Self {
+            year,
+            month,
+            day,
+        })
+    }
+
+    /// `YYYY-MM-DD`, also with `/` separators.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.trim().split(['-', '/']);
+        let year = parts.next()?.parse::<i32>().ok()?;
+        let month = parts.next()?.parse::<u8>().ok()?;
+        let day = parts.next()?.parse::<u8>().ok()?;
+        if parts.next().is_some() || !(1..=9999).contains(&year) {
+            return None;
+        }
+        Self::new(year, month, day)
+    }
+
+    fn from_days(days: i64) -> Self {
+        let (year, month, day) = civil_from_days(days);
+        Self { year, month, day }
+    }
+
+    /// Days since 1970-01-01.
+    pub fn days(self) -> i64 {
+        days_from_civil(self.year, self.month, self.day)
+    }
+
+    /// 0 for Monday through 6 for Sunday.
+    pub fn weekday(self) -> usize {
+        // 1970-01-01 was a Thursday.
+        (self.days() + 3).rem_euclid(7) as usize
+    }
+
+    pub fn add_days(self, days: i64) -> Self {
+        Self::from_days(self.days() + days)
+    }
+
+    /// Same day in another month, clamped to that month's length.
+    pub fn add_months(self, months: i32) -> Self {
+        let index = self.year * 12 + self.month as i32 - 1 + months;
+        let year = index.div_euclid(12);
+        let month = (index.rem_euclid(12) + 1) as u8;
+        Self {
+            year,
+            month,
+            day: self.day.min(days_in_month(year, month)),
+        }
+    }
+
+    pub fn to_text(self) -> String {
+        alloc::format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+fn days_from_civil(year: i32, month: u8, day: u8) -> i64 {
+    let y = year as i64 - if month <= 2 { 1 } else { 0 };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i32, u8, u8) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as i32;
+    (year, month, day)
+}
+
+pub fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+/// Local date and minute of the day from the wall clock.
+pub fn local_now() -> (Date, u16) {
+    let utc_s = crate::timer::wall_clock_unix_millis().div_euclid(1000);
+    let local_s = utc_s + crate::timer::wall_clock_timezone_offset_minutes() as i64 * 60;
+    (
+        Date::from_days(local_s.div_euclid(86_400)),
+        (local_s.rem_euclid(86_400) / 60) as u16,
+    )
+}
+
+pub fn today() -> Date {
+    local_now().0
+}
+
+/// Days of `month` laid out in weeks from Monday; 0 marks cells outside
+/// the month. Six rows fit every month.
+pub fn month_grid(year: i32, month: u8) -> [[u8; 7]; 6] {
+    let mut grid = [[0u8; 7]; 6];
+    let Some(first) = Date::new(year, month, 1) else {
+        return grid;
+    };
+    let offset = first.weekday();
+    for day in 1..=days_in_month(year, month) {
+        let cell = offset + day as usize - 1;
+        grid[cell / 7][cell % 7] = day;
+    }
+    grid
+}
+
+pub fn month_title(year: i32, month: u8) -> String {
+    let name = MONTH_NAMES.get(month.wrapping_sub(1) as usize).copied().unwrap_or("?");
+    alloc::format!("{} {}", name, year)
+}
+
+/// `HH:MM` as minutes after midnight.
+pub fn parse_hhmm(text: &str) -> Option<u16> {
+    let (h, m) = text.trim().split_once(':')?;
+    let (h, m) = (h.parse::<u16>().ok()?, m.parse::<u16>().ok()?);
+    (h < 24 && m < 60 && text.trim().len() <= 5).then_some(h * 60 + m)
+}
+
+pub fn hhmm(minute: u16) -> String {
+    alloc::format!("{:02}:{:02}", minute / 60, minute % 60)
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Event {
+    pub date: Date,
+    /// Minutes after midnight; `None` for all-day events.
+    pub minute: Option<u16>,
+    pub text: String,
+}
+
+impl Event {
+    pub fn time_text(&self) -> String {
+        self.minute.map(hhmm).unwrap_or_else(|| String::from("todo el dia"))
+    }
+}
+
+fn parse_events(text: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.splitn(3, ' ');
+        let (Some(date), Some(time)) = (parts.next().and_then(Date::parse), parts.next()) else {
+            continue;
+        };
+        let minute = if time == ALL_DAY {
+            None
+        } else {
+            match parse_hhmm(time) {
+                Some(minute) => Some(minute),
+                None => continue,
+            }
+        };
+        events.push(Event {
+            date,
+            minute,
+            text: String::from(parts.next().unwrap_or("").trim()),
+        });
+    }
+    sort_events(&mut events);
+    events.truncate(MAX_EVENTS);
+    events
+}
+
+fn serialize_events(events: &[Event]) -> String {
+    let mut out = String::new();
+    for event in events {
+        out.push_str(event.date.to_text().as_str());
+        out.push(' ');
+        match event.minute {
+            Some(minute) => out.push_str(hhmm(minute).as_str()),
+            None => out.push_str(ALL_DAY),
+        }
+        out.push(' ');
+        out.push_str(event.text.as_str());
+        out.push('\n');
+    }
+    out
+}
+
+/// All-day events first, then by time.
+fn sort_events(events: &mut [Event]) {
+    events.sort_by_key(|e| (e.date, e.minute.map(|m| m as i32).unwrap_or(-1)));
+}
+
+/// Events as last read from or written to disk; `None` until loaded.
+static EVENTS: SpinLock<Option<Vec<Event>>> = SpinLock::new(None);
+
+fn fs_ready() -> bool {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get() };
+    fat.init_status == crate::fat32::InitStatus::Success
+}
+
+fn events_dir() -> Result<u32, &'static str> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    fat.ensure_subdirectory(fat.root_cluster, DIR)
+}
+
+/// Read `EVENTS.TXT` the first time events are needed.
+pub fn ensure_loaded() {
+    if EVENTS.lock().is_some() || !fs_ready() {
+        return;
+    }
+    let events = events_dir()
+        .ok()
+        .and_then(|dir| {
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+            fat.read_file_in_dir(dir, EVENTS_FILE).ok()
+        })
+        .map(|raw| parse_events(String::from_utf8_lossy(raw.as_slice()).as_ref()))
+        .unwrap_or_default();
+    let mut cached = EVENTS.lock();
+    if cached.is_none() {
+        *cached = Some(events);
+    }
+}
+
+fn save(events: &[Event]) -> Result<(), &'static str> {
+    let dir = events_dir()?;
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    fat.write_text_file_in_dir(dir, EVENTS_FILE, serialize_events(events).as_bytes())
+}
+
+pub fn events() -> Vec<Event> {
+    ensure_loaded();
+    EVENTS.lock().clone().unwrap_or_default()
+}
+
+pub fn events_on(date: Date) -> Vec<Event> {
+    events().into_iter().filter(|e| e.date == date).collect()
+}
+
+/// Days of the month that have at least one event.
+pub fn busy_days(year: i32, month: u8) -> Vec<u8> {
+    let mut days: Vec<u8> = events()
+        .iter()
+        .filter(|e| e.date.year == year && e.date.month == month)
+        .map(|e| e.date.day)
+        .collect();
+    days.dedup();
+    days
+}
+
+/// Timed events at exactly `minute` of `date`, from the cache only: the
+/// clock service must not touch the disk.
+pub fn due_events(date: Date, minute: u16) -> Vec<Event> {
+    EVENTS
+        .lock()
+        .as_ref()
+        .map(|events| {
+            events
+                .iter()
+                .filter(|e| e.date == date && e.minute == Some(minute))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn add_event(date: Date, minute: Option<u16>, text: &str) -> Result<(), &'static str> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("el evento necesita un texto");
+    }
+    if !fs_ready() {
+        return Err("sin volumen FAT32 para guardar eventos");
+    }
+    let mut events = events();
+    if events.len() >= MAX_EVENTS {
+        return Err("demasiados eventos");
+    }
+    let mut text = String::from(text);
+    while text.len() > TEXT_MAX_BYTES {
+        text.pop();
+    }
+    events.push(Event { date, minute, text });
+    sort_events(&mut events);
+    save(&events)?;
+    *EVENTS.lock() = Some(events);
+    Ok(())
+}
+
+/// Remove the `index`th event of `date` (in `events_on` order).
+pub fn remove_event(date: Date, index: usize) -> Result<Event, &'static str> {
+    let mut events = events();
+    let position = events
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| e.date == date)
+        .nth(index)
+        .map(|(i, _)| i)
+        .ok_or("no hay ese evento")?;
+    let removed = events.remove(position);
+    save(&events)?;
+    *EVENTS.lock() = Some(events);
+    Ok(removed)
+}
+
+/// Month as text, `cal` style: today in brackets, `*` on days with events.
+pub fn month_lines(year: i32, month: u8, today: Date, busy: &[u8]) -> Vec<String> {
+    let mut out = Vec::new();
+    let title = month_title(year, month);
+    out.push(alloc::format!("{:^28}", title));
+    let mut header = String::new();
+    for name in WEEKDAY_NAMES {
+        header.push(' ');
+        header.push_str(name);
+        header.push(' ');
+    }
+    out.push(header);
+    for week in month_grid(year, month) {
+        if week.iter().all(|d| *d == 0) {
+            continue;
+        }
+        let mut line = String::new();
+        for day in week {
+            let cell = if day == 0 {
+                String::from("    ")
+            } else if today == (Date { year, month, day }) {
+                alloc::format!("[{:>2}]", day)
+            } else if busy.contains(&day) {
+                alloc::format!("{:>3}*", day)
+            } else {
+                alloc::format!("{:>3} ", day)
+            };
+            line.push_str(cell.as_str());
+        }
+        out.push(String::from(line.trim_end()));
+    }
+    out
+}
+
+fn event_line(index: usize, event: &Event) -> String {
+    alloc::format!(
+        "{:>3}. {} {:<11} {}",
+        index + 1,
+        event.date.to_text(),
+        event.time_text(),
+        event.text
+    )
+}
+
+/// Month argument: `MM YYYY`, `YYYY-MM` or nothing for the current month.
+fn parse_month(rest: &[&str], today: Date) -> Option<(i32, u8)> {
+    match rest {
+        [] => Some((today.year, today.month)),
+        [ym] if ym.contains('-') => {
+            let (y, m) = ym.split_once('-')?;
+            let (y, m) = (y.parse::<i32>().ok()?, m.parse::<u8>().ok()?);
+            ((1..=12).contains(&m) && (1..=9999).contains(&y)).then_some((y, m))
+        }
+        [m] => {
+            let m = m.parse::<u8>().ok()?;
+            (1..=12).contains(&m).then_some((today.year, m))
+        }
+        [m, y] => {
+            let (m, y) = (m.parse::<u8>().ok()?, y.parse::<i32>().ok()?);
+            ((1..=12).contains(&m) && (1..=9999).contains(&y)).then_some((y, m))
+        }
+        _ => None,
+    }
+}
+
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim();
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let today = today();
+    let mut out = Vec::new();
+    match words.as_slice() {
+        ["add", date, rest @ ..] if !rest.is_empty() => {
+            let Some(date) = Date::parse(date) else {
+                out.push(String::from("cal: fecha invalida (AAAA-MM-DD)"));
+                return out;
+            };
+            let (minute, text) = match parse_hhmm(rest[0]) {
+                Some(minute) => (Some(minute), rest[1..].join(" ")),
+                None => (None, rest.join(" ")),
+            };
+            match add_event(date, minute, text.as_str()) {
+                Ok(()) => out.push(alloc::format!(
+                    "cal: evento guardado el {} ({})",
+                    date.to_text(),
+                    minute.map(hhmm).unwrap_or_else(|| String::from("todo el dia"))
+                )),
+                Err(err) => out.push(alloc::format!("cal: {}", err)),
+            }
+        }
+        ["del", date, n] => match (Date::parse(date), n.parse::<usize>()) {
+            (Some(date), Ok(n)) if n >= 1 => match remove_event(date, n - 1) {
+                Ok(event) => out.push(alloc::format!("cal: borrado '{}'", event.text)),
+                Err(err) => out.push(alloc::format!("cal: {}", err)),
+            },
+            _ => out.push(String::from(USAGE)),
+        },
+        ["events" | "eventos", rest @ ..] => {
+            let all = events();
+            let selected: Vec<&Event> = match rest {
+                [] => all.iter().filter(|e| e.date >= today).take(20).collect(),
+                [day] if Date::parse(day).is_some() => {
+                    let day = Date::parse(day).unwrap_or(today);
+                    all.iter().filter(|e| e.date == day).collect()
+                }
+                _ => match parse_month(rest, today) {
+                    Some((y, m)) => all.iter().filter(|e| e.date.year == y && e.date.month == m).collect(),
+                    None => {
+                        out.push(String::from(USAGE));
+                        return out;
+                    }
+                },
+            };
+            if selected.is_empty() {
+                out.push(String::from("cal: sin eventos"));
+            }
+            for (i, event) in selected.iter().enumerate() {
+                out.push(event_line(i, event));
+            }
+        }
+        rest => match parse_month(rest, today) {
+            Some((year, month)) => {
+                out.extend(month_lines(year, month, today, busy_days(year, month).as_slice()));
+                for (i, event) in events()
+                    .iter()
+                    .filter(|e| e.date.year == year && e.date.month == month)
+                    .enumerate()
+                {
+                    if i == 0 {
+                        out.push(String::new());
+                    }
+                    out.push(event_line(i, event));
+                }
+            }
+            None => out.push(String::from(USAGE)),
+        },
+    }
+    out
+}
+
+const USAGE: &str = "uso: cal [MM [AAAA]|AAAA-MM] | cal add AAAA-MM-DD [HH:MM] <texto> | cal del AAAA-MM-DD <n> | cal events [AAAA-MM-DD|AAAA-MM]";
+
+crate::selftest::kernel_tests! {
+    "calendar";
+
+    fn dates_and_grid() {
+        let date = Date::parse("2026-10-15").ok_or("fecha")?;
+        crate::selftest::ensure_eq(date.weekday(), 3, "jueves")?;
+        crate::selftest::ensure_eq(Date::parse("2024-02-30"), None, "30 de febrero")?;
+        crate::selftest::ensure_eq(Date::parse("2024-02-29").map(|d| d.add_days(1).to_text()), Some(String::from("2024-03-01")), "bisiesto")?;
+        crate::selftest::ensure_eq(date.add_days(-16000).add_days(16000), date, "ida y vuelta")?;
+        let jan31 = Date::new(2027, 1, 31).ok_or("31 de enero")?;
+        crate::selftest::ensure_eq(jan31.add_months(1), Date { year: 2027, month: 2, day: 28 }, "fin de mes")?;
+        crate::selftest::ensure_eq(jan31.add_months(-13).to_text().as_str(), "2025-12-31", "mes anterior")?;
+        let grid = month_grid(2026, 2);
+        // February 2026 starts on a Sunday and needs all six rows.
+        crate::selftest::ensure_eq(grid[0], [0, 0, 0, 0, 0, 0, 1], "primera semana")?;
+        crate::selftest::ensure_eq(grid[4][5], 28, "ultimo dia")?;
+        let lines = month_lines(2026, 10, date, &[20]);
+        crate::selftest::ensure_eq(lines[4].as_str(), " 12  13  14 [15] 16  17  18", "hoy")?;
+        crate::selftest::ensure(lines[5].starts_with(" 19  20*"), "dia con evento")
+    }
+
+    fn events_round_trip() {
+        let text = "# eventos\n2026-12-24 -- Nochebuena\n2026-10-15 09:30 Dentista\n2026-10-15 -- Feria\nbasura\n2026-10-15 25:00 Mal\n";
+        let events = parse_events(text);
+        crate::selftest::ensure_eq(events.len(), 3, "eventos validos")?;
+        crate::selftest::ensure_eq(events[0].text.as_str(), "Feria", "todo el dia primero")?;
+        crate::selftest::ensure_eq(events[1].minute, Some(570), "hora")?;
+        crate::selftest::ensure_eq(parse_events(serialize_events(&events).as_str()), events, "ida y vuelta")?;
+        crate::selftest::ensure_eq(parse_hhmm("7:05"), Some(425), "hora corta")?;
+        crate::selftest::ensure_eq(parse_month(&["3", "2027"], Date { year: 2026, month: 1, day: 1 }), Some((2027, 3)), "mes")
+    }
+}
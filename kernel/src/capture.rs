@@ -0,0 +1,468 @@
+//! Microphone capture: one input stream shared by every reader.
+//!
+//! The first `open` starts the HDA input stream and a `POLL_MS` timer that
+//! drains its DMA buffer into a ring of mono samples at `RATE`; the last
+//! `close` stops both. Readers keep their own cursor in the ring, so the
+//! Settings level meter, the voice recorder and ring 3
+//! (`SYS_AUDIO_CAPTURE_OPEN`, `SYS_AUDIO_CAPTURE_READ`,
+//! `SYS_AUDIO_CAPTURE_CLOSE`) hear the same audio without taking it from
+//! each other. A reader more than `RING_SAMPLES` behind skips ahead and the
+//! skipped samples are counted as lost.
+//!
+//! The voice recorder keeps its take at `RECORD_RATE` (a 3:1 average of the
+//! captured samples) and writes `\RECORDS\VOZnnnn.WAV` when stopped.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::spinlock::SpinLock;
+use crate::timer::TimerId;
+
+pub const RATE: u32 = crate::audio::CAPTURE_RATE;
+/// Four seconds of audio.
+const RING_SAMPLES: usize = RATE as usize * 4;
+const POLL_MS: u64 = 10;
+const MAX_READERS: usize = 8;
+pub const RECORD_RATE: u32 = 16_000;
+const RECORD_DECIMATION: u32 = RATE / RECORD_RATE;
+pub const RECORD_MAX_SECONDS: u32 = 300;
+const RECORD_DIR: &str = "RECORDS";
+/// Level reported for digital silence.
+pub const SILENCE_DB: i32 = -90;
+/// Bottom of the level meter bars.
+pub const METER_FLOOR_DB: i32 = -60;
+/// 10^(-1/20): one decibel down.
+const DB_STEP: f32 = 0.891_250_9;
+
+/// Mono samples with a running count of everything ever pushed, so readers
+/// can tell how far behind they are.
+pub struct SampleRing {
+    samples: Vec<i16>,
+    written: u64,
+}
+
+impl SampleRing {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: alloc::vec![0; capacity.max(1)],
+            written: 0,
+        }
+    }
+
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    pub fn push(&mut self, sample: i16) {
+        let capacity = self.samples.len() as u64;
+        self.samples[(self.written % capacity) as usize] = sample;
+        self.written += 1;
+    }
+
+    /// Copy the samples after `cursor` into `out` and advance it. Returns
+    /// how many were copied and how many were overwritten before the read.
+    pub fn read(&self, cursor: &mut u64, out: &mut [i16]) -> (usize, u64) {
+        let capacity = self.samples.len() as u64;
+        let oldest = self.written.saturating_sub(capacity);
+        let lost = oldest.saturating_sub(*cursor);
+        if lost > 0 {
+            *cursor = oldest;
+        }
+        let count = ((self.written - *cursor) as usize).min(out.len());
+        for (i, slot) in out[..count].iter_mut().enumerate() {
+            *slot = self.samples[((*cursor + i as u64) % capacity) as usize];
+        }
+        *cursor += count as u64;
+        (count, lost)
+    }
+}
+
+/// Average the channels of interleaved 16-bit little endian frames.
+pub fn downmix(bytes: &[u8], channels: usize) -> impl Iterator<Item = i16> + '_ {
+    let channels = channels.max(1);
+    bytes.chunks_exact(channels * 2).map(move |frame| {
+        let sum: i32 = frame
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]) as i32)
+            .sum();
+        (sum / channels as i32) as i16
+    })
+}
+
+/// Peak level of `samples` in whole dBFS, `SILENCE_DB` at the quietest.
+pub fn level_dbfs(samples: &[i16]) -> i32 {
+    let peak = samples.iter().map(|s| s.unsigned_abs()).max().unwrap_or(0) as f32;
+    let mut threshold = 32768.0 * DB_STEP;
+    for db in 0..-SILENCE_DB {
+        if peak >= threshold {
+            return -db;
+        }
+        threshold *= DB_STEP;
+    }
+    SILENCE_DB
+}
+
+/// How full a level meter bar is for `db`, 0..=100.
+pub fn meter_percent(db: i32) -> u32 {
+    ((db.clamp(METER_FLOOR_DB, 0) - METER_FLOOR_DB) * 100 / -METER_FLOOR_DB) as u32
+}
+
+/// Box filter down to `RECORD_RATE`, carrying a partial group across calls.
+#[derive(Default)]
+struct Decimator {
+    sum: i32,
+    count: u32,
+}
+
+impl Decimator {
+    fn push(&mut self, sample: i16, out: &mut Vec<i16>) {
+        self.sum += sample as i32;
+        self.count += 1;
+        if self.count == RECORD_DECIMATION {
+            out.push((self.sum / RECORD_DECIMATION as i32) as i16);
+            self.sum = 0;
+            self.count = 0;
+        }
+    }
+}
+
+struct Reader {
+    id: u32,
+    owner: String,
+    cursor: u64,
+    lost: u64,
+}
+
+struct Recording {
+    samples: Vec<i16>,
+    decimator: Decimator,
+}
+
+struct Capture {
+    ring: Option<SampleRing>,
+    readers: Vec<Reader>,
+    next_id: u32,
+    timer: Option<TimerId>,
+    level_db: i32,
+    recording: Option<Recording>,
+    /// File written by the last finished recording.
+    last_saved: Option<String>,
+    dma: Vec<u8>,
+}
+
+impl Capture {
+    const fn new() -> Self {
+        Self {
+            ring: None,
+            readers: Vec::new(),
+            next_id: 1,
+            timer: None,
+            level_db: SILENCE_DB,
+            recording: None,
+            last_saved: None,
+            dma: Vec::new(),
+        }
+    }
+
+    fn idle(&self) -> bool {
+        self.readers.is_empty() && self.recording.is_none()
+    }
+
+    fn ensure_running(&mut self) -> Result<(), &'static str> {
+        if self.timer.is_some() {
+            return Ok(());
+        }
+        crate::audio::capture_start()?;
+        self.ring = Some(SampleRing::new(RING_SAMPLES));
+        self.level_db = SILENCE_DB;
+        self.timer = Some(crate::timer::every_ms(POLL_MS, on_poll_timer, 0));
+        Ok(())
+    }
+
+    fn stop_if_idle(&mut self) {
+        if !self.idle() {
+            return;
+        }
+        if let Some(timer) = self.timer.take() {
+            crate::timer::cancel(timer);
+        }
+        crate::audio::capture_stop();
+        self.ring = None;
+        self.dma = Vec::new();
+        self.level_db = SILENCE_DB;
+    }
+}
+
+static CAPTURE: SpinLock<Capture> = SpinLock::new(Capture::new());
+
+fn on_poll_timer(_arg: usize) {
+    let mut capture = CAPTURE.lock();
+    let capture = &mut *capture;
+    let Some(ring) = capture.ring.as_mut() else {
+        return;
+    };
+    capture.dma.clear();
+    if crate::audio::capture_poll(&mut capture.dma) == 0 {
+        return;
+    }
+    let mut peak = Vec::with_capacity(capture.dma.len() / 4);
+    for sample in downmix(capture.dma.as_slice(), crate::audio::CAPTURE_CHANNELS as usize) {
+        ring.push(sample);
+        peak.push(sample);
+        if let Some(recording) = capture.recording.as_mut() {
+            if recording.samples.len() < (RECORD_RATE * RECORD_MAX_SECONDS) as usize {
+                recording.decimator.push(sample, &mut recording.samples);
+            }
+        }
+    }
+    capture.level_db = level_dbfs(peak.as_slice());
+}
+
+pub fn available() -> bool {
+    crate::audio::has_capture()
+}
+
+/// Whether the input stream is running.
+pub fn active() -> bool {
+    CAPTURE.lock().timer.is_some()
+}
+
+/// Peak level of the last poll while capture runs.
+pub fn level_db() -> Option<i32> {
+    let capture = CAPTURE.lock();
+    capture.timer.is_some().then_some(capture.level_db)
+}
+
+/// New reader starting at the current position; starts capture if needed.
+pub fn open(owner: &str) -> Result<u32, &'static str> {
+    let mut capture = CAPTURE.lock();
+    if capture.readers.len() >= MAX_READERS {
+        return Err("demasiados lectores");
+    }
+    capture.ensure_running()?;
+    let id = capture.next_id;
+    capture.next_id = capture.next_id.wrapping_add(1).max(1);
+    let cursor = capture.ring.as_ref().map_or(0, SampleRing::written);
+    capture.readers.push(Reader {
+        id,
+        owner: String::from(owner),
+        cursor,
+        lost: 0,
+    });
+    Ok(id)
+}
+
+/// Mono samples at `RATE` captured since the last read, up to `out.len()`.
+pub fn read(owner: &str, id: u32, out: &mut [i16]) -> Result<usize, &'static str> {
+    let mut capture = CAPTURE.lock();
+    let capture = &mut *capture;
+    let reader = capture
+        .readers
+        .iter_mut()
+        .find(|r| r.id == id && r.owner == owner)
+        .ok_or("lector desconocido")?;
+    let Some(ring) = capture.ring.as_ref() else {
+        return Ok(0);
+    };
+    let (count, lost) = ring.read(&mut reader.cursor, out);
+    reader.lost += lost;
+    Ok(count)
+}
+
+pub fn close(owner: &str, id: u32) -> Result<(), &'static str> {
+    let mut capture = CAPTURE.lock();
+    let before = capture.readers.len();
+    capture.readers.retain(|r| !(r.id == id && r.owner == owner));
+    if capture.readers.len() == before {
+        return Err("lector desconocido");
+    }
+    capture.stop_if_idle();
+    Ok(())
+}
+
+pub fn recording() -> bool {
+    CAPTURE.lock().recording.is_some()
+}
+
+/// Length of the take in progress, in milliseconds.
+pub fn recording_ms() -> Option<u64> {
+    let capture = CAPTURE.lock();
+    let recording = capture.recording.as_ref()?;
+    Some(recording.samples.len() as u64 * 1000 / RECORD_RATE as u64)
+}
+
+pub fn last_saved() -> Option<String> {
+    CAPTURE.lock().last_saved.clone()
+}
+
+pub fn start_recording() -> Result<(), &'static str> {
+    let mut capture = CAPTURE.lock();
+    if capture.recording.is_some() {
+        return Err("ya se esta grabando");
+    }
+    capture.ensure_running()?;
+    capture.recording = Some(Recording {
+        samples: Vec::new(),
+        decimator: Decimator::default(),
+    });
+    Ok(())
+}
+
+/// Stop the take and write it out; returns the path written.
+pub fn stop_recording() -> Result<String, &'static str> {
+    let recording = {
+        let mut capture = CAPTURE.lock();
+        let recording = capture.recording.take().ok_or("no se esta grabando")?;
+        capture.stop_if_idle();
+        recording
+    };
+    if recording.samples.is_empty() {
+        return Err("grabacion vacia");
+    }
+    let wav = crate::wav::encode_pcm16(recording.samples.as_slice(), RECORD_RATE, 1);
+    let path = save_wav(wav.as_slice())?;
+    CAPTURE.lock().last_saved = Some(path.clone());
+    Ok(path)
+}
+
+fn save_wav(bytes: &[u8]) -> Result<String, &'static str> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    if fat.init_status != crate::fat32::InitStatus::Success {
+        return Err("volumen de arranque no montado");
+    }
+    let dir = fat.ensure_subdirectory(fat.root_cluster, RECORD_DIR)?;
+    let entries = fat.read_dir_entries(dir)?;
+    let name = (1..10_000)
+        .map(|n| alloc::format!("VOZ{:04}.WAV", n))
+        .find(|name| !entries.iter().any(|e| e.valid && e.matches_name(name)))
+        .ok_or("carpeta de grabaciones llena")?;
+    fat.write_text_file_in_dir(dir, name.as_str(), bytes)?;
+    Ok(alloc::format!("\\{}\\{}", RECORD_DIR, name))
+}
+
+/// Text bar for terminals, `width` cells wide.
+pub fn meter_text(db: i32, width: usize) -> String {
+    let filled = meter_percent(db) as usize * width / 100;
+    let mut bar = String::with_capacity(width + 2);
+    bar.push('[');
+    for i in 0..width {
+        bar.push(if i < filled { '#' } else { '.' });
+    }
+    bar.push(']');
+    bar
+}
+
+/// Shared implementation of `mic [status|rec|stop]`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    match args.trim() {
+        "" | "status" => {
+            if !available() {
+                out.push(String::from("Microfono: sin entrada de audio"));
+                return out;
+            }
+            let capture = CAPTURE.lock();
+            out.push(alloc::format!(
+                "Entrada: {} ({} Hz, {})",
+                crate::audio::capture_input_name(),
+                RATE,
+                if capture.timer.is_some() {
+                    "capturando"
+                } else {
+                    "en reposo"
+                }
+            ));
+            if capture.timer.is_some() {
+                out.push(alloc::format!(
+                    "Nivel: {} {} dB",
+                    meter_text(capture.level_db, 30),
+                    capture.level_db
+                ));
+            }
+            for reader in capture.readers.iter() {
+                out.push(alloc::format!(
+                    "  #{} {} ({} muestras perdidas)",
+                    reader.id,
+                    reader.owner,
+                    reader.lost
+                ));
+            }
+            if let Some(recording) = capture.recording.as_ref() {
+                out.push(alloc::format!(
+                    "Grabando: {}",
+                    crate::wav::format_time((recording.samples.len() as u64 * 1000 / RECORD_RATE as u64) as u32)
+                ));
+            }
+            if let Some(path) = capture.last_saved.as_ref() {
+                out.push(alloc::format!("Ultima grabacion: {}", path));
+            }
+        }
+        "rec" => match start_recording() {
+            Ok(()) => out.push(String::from("Grabando. Usa 'mic stop' para guardar.")),
+            Err(e) => out.push(alloc::format!("mic: {}", e)),
+        },
+        "stop" => match stop_recording() {
+            Ok(path) => out.push(alloc::format!("Grabacion guardada en {}", path)),
+            Err(e) => out.push(alloc::format!("mic: {}", e)),
+        },
+        _ => out.push(String::from("Uso: mic [status|rec|stop]")),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "capture";
+
+    fn ring_readers_skip_ahead_when_overrun() {
+        let mut ring = SampleRing::new(4);
+        for sample in 1..=3 {
+            ring.push(sample);
+        }
+        let mut cursor = 0;
+        let mut out = [0i16; 8];
+        crate::selftest::ensure_eq(ring.read(&mut cursor, &mut out), (3, 0), "primera lectura")?;
+        crate::selftest::ensure_eq(&out[..3], &[1, 2, 3][..], "muestras")?;
+        for sample in 4..=10 {
+            ring.push(sample);
+        }
+        crate::selftest::ensure_eq(ring.read(&mut cursor, &mut out[..2]), (2, 3), "perdidas")?;
+        crate::selftest::ensure_eq(&out[..2], &[7, 8][..], "tras saltar")?;
+        crate::selftest::ensure_eq(ring.read(&mut cursor, &mut out), (2, 0), "resto")?;
+        crate::selftest::ensure_eq(cursor, 10, "cursor")
+    }
+
+    fn levels_are_peak_dbfs() {
+        crate::selftest::ensure_eq(level_dbfs(&[0, 0, 0]), SILENCE_DB, "silencio")?;
+        crate::selftest::ensure_eq(level_dbfs(&[i16::MIN, 5]), 0, "escala completa")?;
+        crate::selftest::ensure_eq(level_dbfs(&[16384, -100]), -6, "mitad")?;
+        crate::selftest::ensure_eq(level_dbfs(&[-3000]), -20, "decima parte")?;
+        crate::selftest::ensure_eq(meter_percent(-30), 50, "barra")?;
+        crate::selftest::ensure_eq(meter_percent(SILENCE_DB), 0, "barra vacia")
+    }
+
+    fn stereo_is_downmixed_and_decimated() {
+        let mut bytes = Vec::new();
+        for (left, right) in [(100i16, 300i16), (-50, -150), (30, 30)] {
+            bytes.extend_from_slice(&left.to_le_bytes());
+            bytes.extend_from_slice(&right.to_le_bytes());
+        }
+        let mono: Vec<i16> = downmix(bytes.as_slice(), 2).collect();
+        crate::selftest::ensure_eq(mono.as_slice(), &[200, -100, 30][..], "mezcla")?;
+        let mut decimator = Decimator::default();
+        let mut out = Vec::new();
+        for sample in mono.iter().chain(mono.iter()).take(5) {
+            decimator.push(*sample, &mut out);
+        }
+        crate::selftest::ensure_eq(out.as_slice(), &[43][..], "promedio 3:1")?;
+        crate::selftest::ensure_eq(decimator.count, 2, "grupo pendiente")
+    }
+
+    fn recordings_are_written_as_mono_wav() {
+        let wav = crate::wav::encode_pcm16(&[1, -2, 3], RECORD_RATE, 1);
+        let parsed = crate::wav::parse_wav(wav.as_slice()).ok_or_else(|| String::from("no se pudo leer"))?;
+        crate::selftest::ensure_eq(parsed.sample_rate, RECORD_RATE, "frecuencia")?;
+        crate::selftest::ensure_eq(parsed.channels, 1, "canales")?;
+        crate::selftest::ensure_eq(parsed.bits_per_sample, 16, "bits")?;
+        crate::selftest::ensure_eq(parsed.data, &[1, 0, 0xFE, 0xFF, 3, 0][..], "datos")
+    }
+}
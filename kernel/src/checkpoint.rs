@@ -0,0 +1,450 @@
+//! Checkpoints of the running Linux compat process: registers, user memory
+//! and open descriptors, written to `\REDUXOS\<name>.CKP`.
+//!
+//! `syscall::linux_shim_checkpoint` captures a single-threaded process
+//! between two run slices and `linux_shim_restore` rewinds the same process
+//! to it, copying back only the pages that changed. A checkpoint is only
+//! restored into the session that took it: the compat runtime maps user
+//! memory at the addresses of the kernel buffers backing it, and those are
+//! not the same after a reboot. Older checkpoints still load for `info`, to
+//! look at the registers and memory of a process that misbehaved.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const MAGIC: &[u8; 4] = b"RCKP";
+const VERSION: u8 = 1;
+const DIR: &str = "REDUXOS";
+const EXTENSION: &str = "CKP";
+const DEFAULT_NAME: &str = "PROC";
+pub const PAGE: usize = 4096;
+
+/// Register names in the order of `Snapshot::regs`.
+pub const REG_NAMES: [&str; 18] = [
+    "rax", "rcx", "rbx", "rbp", "r12", "r13", "r14", "r15", "rdi", "rsi", "rdx", "r10", "r11", "r8", "r9", "rsp",
+    "rip", "rflags",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RegionKind {
+    /// A loaded ELF image, its program headers or TLS block.
+    Image,
+    Stack,
+    Mmap,
+    Brk,
+}
+
+impl RegionKind {
+    fn tag(self) -> u8 {
+        match self {
+            RegionKind::Image => 1,
+            RegionKind::Stack => 2,
+            RegionKind::Mmap => 3,
+            RegionKind::Brk => 4,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            1 => Some(RegionKind::Image),
+            2 => Some(RegionKind::Stack),
+            3 => Some(RegionKind::Mmap),
+            4 => Some(RegionKind::Brk),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RegionKind::Image => "imagen",
+            RegionKind::Stack => "pila",
+            RegionKind::Mmap => "mmap",
+            RegionKind::Brk => "brk",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Region {
+    pub kind: RegionKind,
+    pub addr: u64,
+    pub prot: u64,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Descriptor {
+    pub fd: i32,
+    pub kind: u8,
+    pub cursor: u64,
+    pub flags: u64,
+    /// Runtime files only.
+    pub path: String,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Snapshot {
+    pub session: u64,
+    pub pid: u32,
+    pub tid: u32,
+    pub tick: u64,
+    pub regs: [u64; 18],
+    pub fs_base: u64,
+    pub signal_mask: u64,
+    pub brk_base: u64,
+    pub brk_current: u64,
+    pub regions: Vec<Region>,
+    pub descriptors: Vec<Descriptor>,
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(len)?;
+        let out = self.data.get(self.pos..end)?;
+        self.pos = end;
+        Some(out)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.bytes(8)?.try_into().ok()?))
+    }
+}
+
+impl Snapshot {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.extend_from_slice(&self.session.to_le_bytes());
+        out.extend_from_slice(&self.pid.to_le_bytes());
+        out.extend_from_slice(&self.tid.to_le_bytes());
+        out.extend_from_slice(&self.tick.to_le_bytes());
+        for reg in self.regs.iter() {
+            out.extend_from_slice(&reg.to_le_bytes());
+        }
+        out.extend_from_slice(&self.fs_base.to_le_bytes());
+        out.extend_from_slice(&self.signal_mask.to_le_bytes());
+        out.extend_from_slice(&self.brk_base.to_le_bytes());
+        out.extend_from_slice(&self.brk_current.to_le_bytes());
+        out.extend_from_slice(&(self.regions.len() as u32).to_le_bytes());
+        for region in self.regions.iter() {
+            out.push(region.kind.tag());
+            out.extend_from_slice(&region.addr.to_le_bytes());
+            out.extend_from_slice(&region.prot.to_le_bytes());
+            out.extend_from_slice(&(region.bytes.len() as u64).to_le_bytes());
+            out.extend_from_slice(region.bytes.as_slice());
+        }
+        out.extend_from_slice(&(self.descriptors.len() as u32).to_le_bytes());
+        for fd in self.descriptors.iter() {
+            out.extend_from_slice(&fd.fd.to_le_bytes());
+            out.push(fd.kind);
+            out.extend_from_slice(&fd.cursor.to_le_bytes());
+            out.extend_from_slice(&fd.flags.to_le_bytes());
+            out.extend_from_slice(&(fd.path.len() as u32).to_le_bytes());
+            out.extend_from_slice(fd.path.as_bytes());
+        }
+        let crc = crate::compress::crc32(&out);
+        out.extend_from_slice(&crc.to_le_bytes());
+        out
+    }
+
+    pub fn decode(raw: &[u8]) -> Result<Self, &'static str> {
+        if raw.len() < MAGIC.len() + 1 + 4 || &raw[..4] != MAGIC {
+            return Err("checkpoint no reconocido");
+        }
+        let (body, crc) = raw.split_at(raw.len() - 4);
+        if crate::compress::crc32(body) != u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]]) {
+            return Err("checkpoint corrupto (CRC)");
+        }
+        let mut cur = Cursor {
+            data: &body[4..],
+            pos: 0,
+        };
+        if cur.u8() != Some(VERSION) {
+            return Err("version de checkpoint no soportada");
+        }
+        Self::decode_body(&mut cur).ok_or("checkpoint truncado")
+    }
+
+    fn decode_body(cur: &mut Cursor) -> Option<Self> {
+        let session = cur.u64()?;
+        let pid = cur.u32()?;
+        let tid = cur.u32()?;
+        let tick = cur.u64()?;
+        let mut regs = [0u64; 18];
+        for reg in regs.iter_mut() {
+            *reg = cur.u64()?;
+        }
+        let fs_base = cur.u64()?;
+        let signal_mask = cur.u64()?;
+        let brk_base = cur.u64()?;
+        let brk_current = cur.u64()?;
+        let mut regions = Vec::new();
+        for _ in 0..cur.u32()? {
+            let kind = RegionKind::from_tag(cur.u8()?)?;
+            let addr = cur.u64()?;
+            let prot = cur.u64()?;
+            let len = usize::try_from(cur.u64()?).ok()?;
+            let bytes = cur.bytes(len)?.to_vec();
+            regions.push(Region {
+                kind,
+                addr,
+                prot,
+                bytes,
+            });
+        }
+        let mut descriptors = Vec::new();
+        for _ in 0..cur.u32()? {
+            let fd = cur.u32()? as i32;
+            let kind = cur.u8()?;
+            let cursor = cur.u64()?;
+            let flags = cur.u64()?;
+            let len = cur.u32()? as usize;
+            let path = String::from(core::str::from_utf8(cur.bytes(len)?).ok()?);
+            descriptors.push(Descriptor {
+                fd,
+                kind,
+                cursor,
+                flags,
+                path,
+            });
+        }
+        Some(Self {
+            session,
+            pid,
+            tid,
+            tick,
+            regs,
+            fs_base,
+            signal_mask,
+            brk_base,
+            brk_current,
+            regions,
+            descriptors,
+        })
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        self.regions.iter().map(|region| region.bytes.len()).sum()
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        lines.push(alloc::format!(
+            "sesion {} pid {} tid {} tick {}: {} regiones, {} KiB, {} descriptores",
+            self.session,
+            self.pid,
+            self.tid,
+            self.tick,
+            self.regions.len(),
+            self.memory_bytes() / 1024,
+            self.descriptors.len()
+        ));
+        for pair in REG_NAMES.iter().zip(self.regs.iter()).collect::<Vec<_>>().chunks(4) {
+            let mut line = String::from(" ");
+            for (name, value) in pair {
+                line.push_str(alloc::format!(" {:>6}={:016x}", name, value).as_str());
+            }
+            lines.push(line);
+        }
+        lines.push(alloc::format!(
+            "  fs_base={:016x} brk={:x}..{:x}",
+            self.fs_base,
+            self.brk_base,
+            self.brk_current
+        ));
+        for region in self.regions.iter() {
+            lines.push(alloc::format!(
+                "  {:<6} {:016x} {:>8} bytes prot {:x}",
+                region.kind.name(),
+                region.addr,
+                region.bytes.len(),
+                region.prot
+            ));
+        }
+        for fd in self.descriptors.iter() {
+            lines.push(alloc::format!(
+                "  fd {:<3} tipo {} pos {} {}",
+                fd.fd,
+                fd.kind,
+                fd.cursor,
+                fd.path
+            ));
+        }
+        lines
+    }
+}
+
+/// Copy `src` over process memory at `dst` a page at a time, skipping pages
+/// that are already equal (code pages never differ, and they may be
+/// read-only). Both sides go through `crate::uaccess`. Returns the pages
+/// written.
+pub fn copy_changed_pages(dst: u64, src: &[u8]) -> Result<usize, crate::uaccess::Fault> {
+    let mut current = [0u8; PAGE];
+    let mut written = 0;
+    for (index, src) in src.chunks(PAGE).enumerate() {
+        let addr = dst.checked_add((index * PAGE) as u64).ok_or(crate::uaccess::Fault)?;
+        let current = &mut current[..src.len()];
+        crate::uaccess::copy_from_user(current, addr)?;
+        if current != src {
+            crate::uaccess::copy_to_user(addr, src)?;
+            written += 1;
+        }
+    }
+    Ok(written)
+}
+
+/// 8.3 file name for a checkpoint called `name`.
+pub fn file_name(name: &str) -> Result<String, &'static str> {
+    let name = if name.is_empty() { DEFAULT_NAME } else { name };
+    if name.len() > 8
+        || !name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+    {
+        return Err("nombre invalido (hasta 8 letras, cifras, _ o -)");
+    }
+    Ok(alloc::format!("{}.{}", name.to_ascii_uppercase(), EXTENSION))
+}
+
+fn fat() -> Result<&'static mut crate::fat32::Fat32, &'static str> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    if fat.init_status != crate::fat32::InitStatus::Success {
+        return Err("volumen de arranque no montado");
+    }
+    Ok(fat)
+}
+
+/// Write `snapshot` as `name`; returns the path written.
+pub fn save(name: &str, snapshot: &Snapshot) -> Result<String, &'static str> {
+    let file = file_name(name)?;
+    let fat = fat()?;
+    let dir = fat.ensure_subdirectory(fat.root_cluster, DIR)?;
+    fat.write_text_file_in_dir(dir, file.as_str(), snapshot.encode().as_slice())?;
+    Ok(alloc::format!("\\{}\\{}", DIR, file))
+}
+
+pub fn load(name: &str) -> Result<Snapshot, &'static str> {
+    let file = file_name(name)?;
+    let fat = fat()?;
+    let dir = fat.ensure_subdirectory(fat.root_cluster, DIR)?;
+    let raw = fat
+        .read_file_in_dir(dir, file.as_str())
+        .map_err(|_| "checkpoint no encontrado")?;
+    Snapshot::decode(raw.as_slice())
+}
+
+/// Shared implementation of `checkpoint [save|restore|info] [nombre]`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let name = words.get(1).copied().unwrap_or("");
+    match words.first().copied() {
+        Some("save") if words.len() <= 2 => {
+            let saved = crate::syscall::linux_shim_checkpoint().and_then(|snapshot| {
+                let path = save(name, &snapshot)?;
+                Ok((snapshot, path))
+            });
+            match saved {
+                Ok((snapshot, path)) => out.push(alloc::format!(
+                    "checkpoint: pid {} guardado en {} ({} KiB de memoria)",
+                    snapshot.pid,
+                    path,
+                    snapshot.memory_bytes() / 1024
+                )),
+                Err(e) => out.push(alloc::format!("checkpoint: {}", e)),
+            }
+        }
+        Some("restore") if words.len() <= 2 => {
+            match load(name).and_then(|snapshot| crate::syscall::linux_shim_restore(&snapshot)) {
+                Ok((pages, skipped)) => {
+                    out.push(alloc::format!(
+                        "checkpoint: proceso restaurado ({} paginas escritas)",
+                        pages
+                    ));
+                    if skipped > 0 {
+                        out.push(alloc::format!(
+                            "checkpoint: {} descriptores ya no estan abiertos y se dejaron como estan",
+                            skipped
+                        ));
+                    }
+                }
+                Err(e) => out.push(alloc::format!("checkpoint: {}", e)),
+            }
+        }
+        None | Some("info") if words.len() <= 2 => match load(name) {
+            Ok(snapshot) => out.extend(snapshot.describe()),
+            Err(e) => out.push(alloc::format!("checkpoint: {}", e)),
+        },
+        _ => out.push(String::from("Uso: checkpoint [save|restore|info] [nombre]")),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "checkpoint";
+
+    fn round_trips_snapshots() {
+        let mut regs = [0u64; 18];
+        regs[16] = 0x40_1000;
+        let snapshot = Snapshot {
+            session: 3,
+            pid: 1003,
+            tid: 2003,
+            tick: 99,
+            regs,
+            fs_base: 0x7000,
+            signal_mask: 1 << 2,
+            brk_base: 0x10_0000,
+            brk_current: 0x10_2000,
+            regions: alloc::vec![Region {
+                kind: RegionKind::Brk,
+                addr: 0x10_0000,
+                prot: 3,
+                bytes: alloc::vec![7u8; 10],
+            }],
+            descriptors: alloc::vec![Descriptor {
+                fd: 3,
+                kind: 1,
+                cursor: 42,
+                flags: 0,
+                path: String::from("/etc/hosts"),
+            }],
+        };
+        let mut raw = snapshot.encode();
+        crate::selftest::ensure_eq(Snapshot::decode(raw.as_slice()), Ok(snapshot.clone()), "ida y vuelta")?;
+        raw[20] ^= 1;
+        crate::selftest::ensure(Snapshot::decode(raw.as_slice()).is_err(), "crc")?;
+        crate::selftest::ensure(Snapshot::decode(b"RCKP").is_err(), "truncado")
+    }
+
+    fn copies_only_changed_pages() {
+        let mut dst = alloc::vec![0u8; PAGE * 3];
+        let mut src = dst.clone();
+        src[PAGE + 5] = 1;
+        let addr = dst.as_mut_ptr() as u64;
+        crate::selftest::ensure_eq(copy_changed_pages(addr, &src), Ok(1), "una pagina")?;
+        crate::selftest::ensure_eq(dst[PAGE + 5], 1, "copiada")?;
+        crate::selftest::ensure_eq(copy_changed_pages(addr, &src), Ok(0), "sin cambios")
+    }
+
+    fn names_checkpoint_files() {
+        crate::selftest::ensure_eq(file_name(""), Ok(String::from("PROC.CKP")), "por defecto")?;
+        crate::selftest::ensure_eq(file_name("edit_1"), Ok(String::from("EDIT_1.CKP")), "nombre")?;
+        crate::selftest::ensure(file_name("demasiadolargo").is_err(), "largo")?;
+        crate::selftest::ensure(file_name("a.b").is_err(), "punto")
+    }
+}
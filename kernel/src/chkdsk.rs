@@ -0,0 +1,566 @@
+//! `chkdsk`: check and repair FAT32 cluster chains.
+//!
+//! Walks the directory tree from the root and follows every chain in the
+//! FAT, then looks for allocated clusters nobody reached. It finds:
+//!
+//! - chains that end in a free cluster, a bad-cluster mark or a number
+//!   outside the volume (cut at the last good cluster);
+//! - clusters claimed twice, by two files or by a loop (cut where the
+//!   second claim starts);
+//! - files whose chain is longer than their size (the tail is freed) or
+//!   shorter (the size shrinks to what the chain holds);
+//! - lost clusters, allocated but in no chain (freed).
+//!
+//! With `reparar` the fixes are written as journaled metadata updates
+//! (`crate::fsjournal`), together with a free-cluster count in FSInfo
+//! recounted from the FAT; without it nothing is written. Folders whose first
+//! cluster is unusable are only reported.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::fat32::Fat32;
+
+const FREE: u32 = 0;
+const BAD: u32 = 0x0FFF_FFF7;
+const EOC_MIN: u32 = 0x0FFF_FFF8;
+const EOC: u32 = 0x0FFF_FFFF;
+const ATTR_LFN: u8 = 0x0F;
+const ATTR_VOLUME: u8 = 0x08;
+const ATTR_DIR: u8 = 0x10;
+const SECTOR_SIZE: usize = 512;
+/// FAT sectors read at once.
+const FAT_CACHE_SECTORS: usize = 32;
+
+/// A directory entry as `chkdsk` sees it.
+#[derive(Clone, Debug)]
+pub struct Item {
+    /// Sector and slot of the 32-byte entry.
+    pub lba: u64,
+    pub slot: usize,
+    pub name: String,
+    pub first: u32,
+    pub size: u32,
+    pub is_dir: bool,
+}
+
+/// What `check` needs from a volume.
+pub trait Volume {
+    fn root(&self) -> u32;
+    /// Valid cluster numbers are `2..clusters()`.
+    fn clusters(&self) -> u32;
+    fn cluster_bytes(&self) -> u32;
+    fn next(&mut self, cluster: u32) -> Result<u32, &'static str>;
+    fn set_next(&mut self, cluster: u32, value: u32) -> Result<(), &'static str>;
+    /// Entries of the folder stored in `chain`, without `.` and `..`.
+    fn entries(&mut self, chain: &[u32]) -> Result<Vec<Item>, &'static str>;
+    /// Write `item.first` and `item.size` back to its entry.
+    fn update(&mut self, item: &Item) -> Result<(), &'static str>;
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Report {
+    pub dirs: usize,
+    pub files: usize,
+    pub used_clusters: usize,
+    pub broken_chains: usize,
+    pub cross_links: usize,
+    pub size_fixes: usize,
+    pub lost_clusters: usize,
+    pub damaged_dirs: usize,
+    pub repaired: bool,
+}
+
+impl Report {
+    pub fn problems(&self) -> usize {
+        self.broken_chains + self.cross_links + self.size_fixes + self.lost_clusters + self.damaged_dirs
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        out.push(alloc::format!(
+            "{} carpetas, {} archivos, {} clusters en uso",
+            self.dirs,
+            self.files,
+            self.used_clusters
+        ));
+        let verb = if self.repaired { "reparado" } else { "encontrado" };
+        for (count, what) in [
+            (self.broken_chains, "cadenas rotas"),
+            (self.cross_links, "clusters compartidos o en bucle"),
+            (self.size_fixes, "tamanos que no cuadran con su cadena"),
+            (self.lost_clusters, "clusters perdidos"),
+        ] {
+            if count > 0 {
+                out.push(alloc::format!("{}: {} {}", verb, count, what));
+            }
+        }
+        if self.damaged_dirs > 0 {
+            out.push(alloc::format!(
+                "{} carpetas con cluster inicial invalido (no se tocan)",
+                self.damaged_dirs
+            ));
+        }
+        if self.problems() == 0 {
+            out.push(String::from("sin errores"));
+        } else if !self.repaired {
+            out.push(String::from("usa 'chkdsk reparar' para corregirlo"));
+        }
+        out
+    }
+}
+
+struct Bitmap(Vec<u64>);
+
+impl Bitmap {
+    fn new(bits: u32) -> Self {
+        Self(alloc::vec![0; (bits as usize).div_ceil(64)])
+    }
+
+    fn get(&self, bit: u32) -> bool {
+        self.0[bit as usize / 64] & (1 << (bit % 64)) != 0
+    }
+
+    fn set(&mut self, bit: u32, on: bool) {
+        let word = &mut self.0[bit as usize / 64];
+        if on {
+            *word |= 1 << (bit % 64);
+        } else {
+            *word &= !(1 << (bit % 64));
+        }
+    }
+}
+
+/// Follow the chain from `first`, claiming its clusters in `used`. Returns
+/// the clusters and whether it had to stop early (`Some(cross_linked)`).
+fn walk(vol: &mut impl Volume, used: &mut Bitmap, first: u32) -> Result<(Vec<u32>, Option<bool>), &'static str> {
+    let mut chain = Vec::new();
+    let mut cluster = first;
+    loop {
+        if cluster < 2 || cluster >= vol.clusters() {
+            return Ok((chain, Some(false)));
+        }
+        if used.get(cluster) {
+            return Ok((chain, Some(true)));
+        }
+        let next = vol.next(cluster)?;
+        if next == FREE || next == BAD {
+            return Ok((chain, Some(false)));
+        }
+        used.set(cluster, true);
+        chain.push(cluster);
+        if next >= EOC_MIN {
+            return Ok((chain, None));
+        }
+        cluster = next;
+    }
+}
+
+/// Check the volume and, with `repair`, fix what was found.
+pub fn check(vol: &mut impl Volume, repair: bool) -> Result<Report, &'static str> {
+    let mut report = Report {
+        repaired: repair,
+        ..Report::default()
+    };
+    let mut used = Bitmap::new(vol.clusters());
+    let cluster_bytes = vol.cluster_bytes().max(1) as usize;
+
+    let root = vol.root();
+    let (root_chain, problem) = walk(vol, &mut used, root)?;
+    if root_chain.is_empty() {
+        return Err("la carpeta raiz no tiene una cadena valida");
+    }
+    if let Some(cross) = problem {
+        if cross {
+            report.cross_links += 1;
+        } else {
+            report.broken_chains += 1;
+        }
+        if repair {
+            vol.set_next(*root_chain.last().unwrap_or(&root), EOC)?;
+        }
+    }
+    let mut pending = alloc::vec![root_chain];
+
+    while let Some(dir_chain) = pending.pop() {
+        report.dirs += 1;
+        for mut item in vol.entries(&dir_chain)? {
+            if !item.is_dir {
+                report.files += 1;
+            }
+            if item.first == 0 {
+                if !item.is_dir && item.size > 0 {
+                    report.size_fixes += 1;
+                    if repair {
+                        item.size = 0;
+                        vol.update(&item)?;
+                    }
+                }
+                continue;
+            }
+            let (mut chain, problem) = walk(vol, &mut used, item.first)?;
+            if let Some(cross) = problem {
+                if cross {
+                    report.cross_links += 1;
+                } else {
+                    report.broken_chains += 1;
+                }
+                if repair {
+                    match chain.last() {
+                        Some(last) => vol.set_next(*last, EOC)?,
+                        None if !item.is_dir => {
+                            item.first = 0;
+                            item.size = 0;
+                            vol.update(&item)?;
+                        }
+                        None => {}
+                    }
+                }
+            }
+            if item.is_dir {
+                if chain.is_empty() {
+                    report.damaged_dirs += 1;
+                } else {
+                    pending.push(chain);
+                }
+                continue;
+            }
+
+            let needed = (item.size as usize).div_ceil(cluster_bytes);
+            if chain.len() > needed {
+                report.size_fixes += 1;
+                if repair {
+                    for cluster in chain[needed..].iter() {
+                        vol.set_next(*cluster, FREE)?;
+                        used.set(*cluster, false);
+                    }
+                    match needed {
+                        0 => {
+                            item.first = 0;
+                            vol.update(&item)?;
+                        }
+                        n => vol.set_next(chain[n - 1], EOC)?,
+                    }
+                    chain.truncate(needed);
+                }
+            } else if chain.len() < needed && !chain.is_empty() {
+                report.size_fixes += 1;
+                if repair {
+                    item.size = (chain.len() * cluster_bytes).min(u32::MAX as usize) as u32;
+                    vol.update(&item)?;
+                }
+            }
+        }
+    }
+
+    for cluster in 2..vol.clusters() {
+        if used.get(cluster) {
+            report.used_clusters += 1;
+            continue;
+        }
+        let value = vol.next(cluster)?;
+        if value != FREE && value != BAD {
+            report.lost_clusters += 1;
+            if repair {
+                vol.set_next(cluster, FREE)?;
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// `Volume` over a mounted FAT32 `Fat32`.
+struct FatVolume<'a> {
+    fat: &'a mut Fat32,
+    /// First FAT sector held in `cache`, if any.
+    cache_lba: Option<u64>,
+    cache: Vec<u8>,
+}
+
+impl FatVolume<'_> {
+    fn short_name(raw: &[u8]) -> String {
+        let base = core::str::from_utf8(&raw[..8]).unwrap_or("?").trim_end();
+        let ext = core::str::from_utf8(&raw[8..11]).unwrap_or("").trim_end();
+        if ext.is_empty() {
+            String::from(base)
+        } else {
+            alloc::format!("{}.{}", base, ext)
+        }
+    }
+}
+
+impl Volume for FatVolume<'_> {
+    fn root(&self) -> u32 {
+        self.fat.root_cluster
+    }
+
+    fn clusters(&self) -> u32 {
+        self.fat.fat_entry_count()
+    }
+
+    fn cluster_bytes(&self) -> u32 {
+        self.fat.sectors_per_cluster as u32 * SECTOR_SIZE as u32
+    }
+
+    fn next(&mut self, cluster: u32) -> Result<u32, &'static str> {
+        let (lba, offset) = self.fat.fat_entry_sector(cluster).ok_or("FAT index overflow")?;
+        let fat_start = self.fat.fat_start;
+        let block = fat_start + (lba - fat_start) / FAT_CACHE_SECTORS as u64 * FAT_CACHE_SECTORS as u64;
+        if self.cache_lba != Some(block) {
+            let fat_end = fat_start + self.fat.sectors_per_fat as u64;
+            let sectors = (fat_end.saturating_sub(block) as usize).clamp(1, FAT_CACHE_SECTORS);
+            self.cache.resize(sectors * SECTOR_SIZE, 0);
+            if !self.fat.read_metadata_span(block, sectors, &mut self.cache) {
+                self.cache_lba = None;
+                return Err("FAT read error");
+            }
+            self.cache_lba = Some(block);
+        }
+        let at = (lba - block) as usize * SECTOR_SIZE + offset;
+        let raw = [
+            self.cache[at],
+            self.cache[at + 1],
+            self.cache[at + 2],
+            self.cache[at + 3],
+        ];
+        Ok(u32::from_le_bytes(raw) & 0x0FFF_FFFF)
+    }
+
+    fn set_next(&mut self, cluster: u32, value: u32) -> Result<(), &'static str> {
+        self.fat.set_fat_entry(cluster, value)?;
+        self.cache_lba = None;
+        Ok(())
+    }
+
+    fn entries(&mut self, chain: &[u32]) -> Result<Vec<Item>, &'static str> {
+        let mut out = Vec::new();
+        for cluster in chain.iter() {
+            let base = self.fat.cluster_lba(*cluster);
+            for sec in 0..self.fat.sectors_per_cluster as u64 {
+                let mut sector = [0u8; SECTOR_SIZE];
+                if !self.fat.read_metadata_sector(base + sec, &mut sector) {
+                    return Err("Directory read failed");
+                }
+                for slot in 0..SECTOR_SIZE / 32 {
+                    let raw = &sector[slot * 32..slot * 32 + 32];
+                    match raw[0] {
+                        0 => return Ok(out),
+                        0xE5 | b'.' => continue,
+                        _ => {}
+                    }
+                    let attr = raw[11];
+                    if attr == ATTR_LFN || attr & ATTR_VOLUME != 0 {
+                        continue;
+                    }
+                    let hi = u16::from_le_bytes([raw[20], raw[21]]) as u32;
+                    let lo = u16::from_le_bytes([raw[26], raw[27]]) as u32;
+                    out.push(Item {
+                        lba: base + sec,
+                        slot,
+                        name: Self::short_name(raw),
+                        first: (hi << 16) | lo,
+                        size: u32::from_le_bytes([raw[28], raw[29], raw[30], raw[31]]),
+                        is_dir: attr & ATTR_DIR != 0,
+                    });
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    fn update(&mut self, item: &Item) -> Result<(), &'static str> {
+        let mut sector = [0u8; SECTOR_SIZE];
+        if !self.fat.read_metadata_sector(item.lba, &mut sector) {
+            return Err("Directory read failed");
+        }
+        let raw = &mut sector[item.slot * 32..item.slot * 32 + 32];
+        raw[20..22].copy_from_slice(&((item.first >> 16) as u16).to_le_bytes());
+        raw[26..28].copy_from_slice(&(item.first as u16).to_le_bytes());
+        raw[28..32].copy_from_slice(&item.size.to_le_bytes());
+        if self.fat.write_metadata_sector(item.lba, &sector) {
+            Ok(())
+        } else {
+            Err("Directory write failed")
+        }
+    }
+}
+
+/// Check the mounted FAT32 volume `fat`.
+pub fn check_fat(fat: &mut Fat32, repair: bool) -> Result<Report, &'static str> {
+    if fat.init_status != crate::fat32::InitStatus::Success {
+        return Err("sin volumen montado");
+    }
+    if fat.mounted_fs != crate::fat32::DetectedFsKind::Fat32 {
+        return Err("solo volumenes FAT32");
+    }
+    if repair {
+        fat.begin_tx();
+    }
+    let result = {
+        let mut vol = FatVolume {
+            fat: &mut *fat,
+            cache_lba: None,
+            cache: Vec::new(),
+        };
+        check(&mut vol, repair)
+    };
+    if repair {
+        // Lost clusters were freed behind the old count; take the FAT's word.
+        if result.is_ok() {
+            fat.recount_free_clusters();
+        }
+        fat.commit_tx();
+        if result.as_ref().is_ok_and(|r| r.problems() > 0) {
+            crate::fswatch::emit(fat.volume_id(), fat.root_cluster, "", crate::fswatch::Change::Overflow);
+        }
+    }
+    result
+}
+
+/// `chkdsk [reparar]`.
+pub fn command_lines(fat: &mut Fat32, args: &str) -> Vec<String> {
+    let repair = match args.trim() {
+        "" => false,
+        "reparar" | "/f" => true,
+        _ => return alloc::vec![String::from("uso: chkdsk [reparar]")],
+    };
+    if repair && !crate::perm::acting().is_root() {
+        return alloc::vec![String::from("chkdsk: reparar necesita root (su root)")];
+    }
+    match check_fat(fat, repair) {
+        Ok(report) => report.lines(),
+        Err(e) => alloc::vec![alloc::format!("chkdsk: {}", e)],
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "chkdsk";
+
+    fn clean_volume_has_no_problems() {
+        let mut vol = MemVolume::new(64, 512);
+        vol.chain(&[2]);
+        let a = vol.chain(&[10, 11]);
+        vol.file(2, "A.TXT", a, 700);
+        let report = check(&mut vol, false)?;
+        crate::selftest::ensure_eq(report.problems(), 0, "problemas")?;
+        crate::selftest::ensure_eq(report.files, 1, "archivos")?;
+        crate::selftest::ensure_eq(report.used_clusters, 3, "clusters")
+    }
+
+    fn repairs_chains_and_sizes() {
+        let mut vol = MemVolume::new(64, 512);
+        vol.chain(&[2]);
+        // Longer than its size: 3 clusters for 100 bytes.
+        let long = vol.chain(&[10, 11, 12]);
+        vol.file(2, "LARGO.TXT", long, 100);
+        // Cross-linked into the first file's chain, so also short.
+        let cross = vol.chain(&[20]);
+        vol.fat[20] = 11;
+        vol.file(2, "CRUZADO.TXT", cross, 1024);
+        // Points past the end of the volume.
+        let broken = vol.chain(&[30]);
+        vol.fat[30] = 500;
+        vol.file(2, "ROTO.TXT", broken, 512);
+        // Short: one cluster for 2000 bytes.
+        let short = vol.chain(&[40]);
+        vol.file(2, "CORTO.TXT", short, 2000);
+        // Allocated, never linked.
+        vol.chain(&[50, 51]);
+
+        let found = check(&mut vol, false)?;
+        crate::selftest::ensure_eq(found.lost_clusters, 2, "perdidos")?;
+        crate::selftest::ensure_eq(found.cross_links, 1, "cruzados")?;
+        crate::selftest::ensure_eq(found.broken_chains, 1, "rotos")?;
+        crate::selftest::ensure_eq(found.size_fixes, 3, "tamanos")?;
+        crate::selftest::ensure_eq(vol.fat[50], 51, "solo mirar no escribe")?;
+
+        check(&mut vol, true)?;
+        crate::selftest::ensure_eq(vol.fat[10], EOC, "cadena recortada")?;
+        crate::selftest::ensure_eq(vol.fat[11], FREE, "cola liberada")?;
+        crate::selftest::ensure_eq(vol.fat[50], FREE, "perdido liberado")?;
+        crate::selftest::ensure_eq(vol.items[3].size, 512, "tamano ajustado")?;
+        let again = check(&mut vol, false)?;
+        crate::selftest::ensure_eq(again.problems(), 0, "limpio tras reparar")
+    }
+}
+
+/// In-memory volume for the tests.
+#[cfg(feature = "selftest")]
+struct MemVolume {
+    fat: Vec<u32>,
+    cluster_bytes: u32,
+    /// (folder first cluster, entry).
+    items: Vec<Item>,
+    parents: Vec<u32>,
+}
+
+#[cfg(feature = "selftest")]
+impl MemVolume {
+    fn new(clusters: usize, cluster_bytes: u32) -> Self {
+        Self {
+            fat: alloc::vec![FREE; clusters],
+            cluster_bytes,
+            items: Vec::new(),
+            parents: Vec::new(),
+        }
+    }
+
+    fn chain(&mut self, clusters: &[u32]) -> u32 {
+        for pair in clusters.windows(2) {
+            self.fat[pair[0] as usize] = pair[1];
+        }
+        self.fat[*clusters.last().unwrap_or(&0) as usize] = EOC;
+        clusters[0]
+    }
+
+    fn file(&mut self, dir: u32, name: &str, first: u32, size: u32) {
+        self.items.push(Item {
+            lba: 0,
+            slot: self.items.len(),
+            name: String::from(name),
+            first,
+            size,
+            is_dir: false,
+        });
+        self.parents.push(dir);
+    }
+}
+
+#[cfg(feature = "selftest")]
+impl Volume for MemVolume {
+    fn root(&self) -> u32 {
+        2
+    }
+
+    fn clusters(&self) -> u32 {
+        self.fat.len() as u32
+    }
+
+    fn cluster_bytes(&self) -> u32 {
+        self.cluster_bytes
+    }
+
+    fn next(&mut self, cluster: u32) -> Result<u32, &'static str> {
+        Ok(self.fat[cluster as usize])
+    }
+
+    fn set_next(&mut self, cluster: u32, value: u32) -> Result<(), &'static str> {
+        self.fat[cluster as usize] = value;
+        Ok(())
+    }
+
+    fn entries(&mut self, chain: &[u32]) -> Result<Vec<Item>, &'static str> {
+        Ok(self
+            .items
+            .iter()
+            .zip(self.parents.iter())
+            .filter(|(_, dir)| Some(*dir) == chain.first())
+            .map(|(item, _)| item.clone())
+            .collect())
+    }
+
+    fn update(&mut self, item: &Item) -> Result<(), &'static str> {
+        self.items[item.slot] = item.clone();
+        Ok(())
+    }
+}
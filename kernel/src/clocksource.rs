@@ -0,0 +1,444 @@
+//! Clocksources behind the monotonic nanosecond clock.
+//!
+//! `init` probes the invariant TSC (CPUID 0x8000_0007), the HPET (ACPI
+//! `HPET` table) and the ACPI PM timer (FADT), measures the TSC against the
+//! best reference it found (HPET, then PM timer, then PIT channel 2, then a
+//! firmware stall) and picks the source for [`monotonic_ns`]:
+//!
+//! 1. invariant TSC: cheapest to read and steady across P-states;
+//! 2. HPET;
+//! 3. ACPI PM timer;
+//! 4. a TSC that is not invariant, still finer than ticks;
+//! 5. timer ticks, which is also what the clock runs on until `init`.
+//!
+//! Narrow counters (a 32-bit HPET, the 24-bit PM timer) are widened in
+//! software and have to be read at least once per wrap, which the GUI and
+//! shell loops do many times a second.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::__cpuid;
+
+use crate::hal::{inb, inl, outb};
+use crate::spinlock::SpinLock;
+use crate::sync::Once;
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+const FS_PER_NS: u64 = 1_000_000;
+const PM_TIMER_HZ: u64 = 3_579_545;
+const PIT_HZ: u64 = 1_193_182;
+const CALIBRATION_MS: u64 = 20;
+/// Give up on a reference that has not advanced after this many TSC cycles
+/// (several seconds even on fast parts).
+const CALIBRATION_SPIN_LIMIT: u64 = 20_000_000_000;
+
+const HPET_GCAP_ID: u64 = 0x000;
+const HPET_GEN_CONF: u64 = 0x010;
+const HPET_MAIN_COUNTER: u64 = 0x0F0;
+const HPET_ENABLE_CNF: u64 = 1;
+const HPET_COUNT_SIZE_CAP: u64 = 1 << 13;
+/// The spec caps the HPET period at 100 ns.
+const HPET_MAX_PERIOD_FS: u64 = 100_000_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    Tsc,
+    Hpet,
+    AcpiPm,
+    Ticks,
+}
+
+impl Kind {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Kind::Tsc => "tsc",
+            Kind::Hpet => "hpet",
+            Kind::AcpiPm => "acpi_pm",
+            Kind::Ticks => "ticks",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Hpet {
+    base: u64,
+    period_fs: u64,
+    wide: bool,
+}
+
+impl Hpet {
+    fn probe() -> Option<Self> {
+        let table = crate::acpi::find_table(b"HPET")?;
+        // Base address: generic address structure at offset 40, system memory only.
+        let space = unsafe { core::ptr::read_unaligned((table + 40) as *const u8) };
+        let base = unsafe { core::ptr::read_unaligned((table + 44) as *const u64) };
+        if space != 0 || base == 0 || !crate::paging::kernel_page_mapped(base) {
+            return None;
+        }
+        let caps = unsafe { ((base + HPET_GCAP_ID) as *const u64).read_volatile() };
+        let period_fs = caps >> 32;
+        if period_fs == 0 || period_fs > HPET_MAX_PERIOD_FS {
+            return None;
+        }
+        unsafe {
+            let conf = (base + HPET_GEN_CONF) as *mut u64;
+            let value = conf.read_volatile();
+            if value & HPET_ENABLE_CNF == 0 {
+                conf.write_volatile(value | HPET_ENABLE_CNF);
+            }
+        }
+        Some(Self {
+            base,
+            period_fs,
+            wide: caps & HPET_COUNT_SIZE_CAP != 0,
+        })
+    }
+
+    fn read(&self) -> u64 {
+        unsafe { ((self.base + HPET_MAIN_COUNTER) as *const u64).read_volatile() }
+    }
+
+    fn bits(&self) -> u32 {
+        if self.wide {
+            64
+        } else {
+            32
+        }
+    }
+
+    fn hz(&self) -> u64 {
+        1_000_000_000_000_000 / self.period_fs
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct PmTimer {
+    port: u16,
+    bits: u32,
+}
+
+impl PmTimer {
+    fn probe() -> Option<Self> {
+        let (port, wide) = crate::acpi::pm_timer()?;
+        Some(Self {
+            port,
+            bits: if wide { 32 } else { 24 },
+        })
+    }
+
+    fn read(&self) -> u64 {
+        let mask = if self.bits == 32 { u32::MAX } else { 0x00FF_FFFF };
+        (unsafe { inl(self.port) } & mask) as u64
+    }
+}
+
+/// What the TSC frequency was measured against.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Reference {
+    Hpet,
+    AcpiPm,
+    Pit,
+    FirmwareStall,
+}
+
+impl Reference {
+    const fn name(self) -> &'static str {
+        match self {
+            Reference::Hpet => "hpet",
+            Reference::AcpiPm => "acpi_pm",
+            Reference::Pit => "pit",
+            Reference::FirmwareStall => "uefi stall",
+        }
+    }
+}
+
+/// Counter to nanoseconds as `(count * mult) >> 32`.
+fn mult_for_hz(hz: u64) -> u64 {
+    (((NS_PER_SEC as u128) << 32) / hz.max(1) as u128) as u64
+}
+
+fn scale(count: u64, mult: u64) -> u64 {
+    ((count as u128 * mult as u128) >> 32) as u64
+}
+
+/// Software extension of a counter narrower than 64 bits.
+struct Widened {
+    last: u64,
+    high: u64,
+}
+
+static WIDENED: SpinLock<Widened> = SpinLock::new(Widened { last: 0, high: 0 });
+
+fn widen(raw: u64, bits: u32) -> u64 {
+    if bits >= 64 {
+        return raw;
+    }
+    let mut state = WIDENED.lock();
+    if raw < state.last {
+        state.high = state.high.wrapping_add(1u64 << bits);
+    }
+    state.last = raw;
+    state.high | raw
+}
+
+struct Selected {
+    kind: Kind,
+    hpet: Option<Hpet>,
+    pm: Option<PmTimer>,
+    mult: u64,
+    /// Counter value and clock reading at the switch, so the clock carries
+    /// on from where the tick-based one was.
+    base_count: u64,
+    base_ns: u64,
+    tsc_invariant: bool,
+    reference: Option<Reference>,
+}
+
+impl Selected {
+    fn count(&self) -> u64 {
+        match self.kind {
+            Kind::Tsc => crate::hal::rdtsc(),
+            Kind::Hpet => self.hpet.map(|h| widen(h.read(), h.bits())).unwrap_or(0),
+            Kind::AcpiPm => self.pm.map(|p| widen(p.read(), p.bits)).unwrap_or(0),
+            Kind::Ticks => 0,
+        }
+    }
+
+    fn now_ns(&self) -> u64 {
+        if self.kind == Kind::Ticks {
+            return ticks_ns();
+        }
+        self.base_ns
+            .saturating_add(scale(self.count().wrapping_sub(self.base_count), self.mult))
+    }
+}
+
+static SELECTED: Once<Selected> = Once::new();
+static TSC_HZ: AtomicU64 = AtomicU64::new(0);
+
+fn ticks_ns() -> u64 {
+    crate::timer::snapshot().uptime_ms.saturating_mul(1_000_000)
+}
+
+fn tsc_invariant() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let max_ext = unsafe { __cpuid(0x8000_0000) }.eax;
+        max_ext >= 0x8000_0007 && unsafe { __cpuid(0x8000_0007) }.edx & (1 << 8) != 0
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        false
+    }
+}
+
+/// TSC cycles elapsed while the counter behind `read` advanced by `target`
+/// counts, and the counts it actually advanced by.
+fn measure(read: impl Fn() -> u64, bits: u32, target: u64) -> Option<(u64, u64)> {
+    let mask = if bits >= 64 { u64::MAX } else { (1u64 << bits) - 1 };
+    let start = read();
+    let tsc_start = crate::hal::rdtsc();
+    loop {
+        let now = read();
+        let tsc_now = crate::hal::rdtsc();
+        let elapsed = now.wrapping_sub(start) & mask;
+        if elapsed >= target {
+            return Some((tsc_now.wrapping_sub(tsc_start), elapsed));
+        }
+        if tsc_now.wrapping_sub(tsc_start) > CALIBRATION_SPIN_LIMIT {
+            return None;
+        }
+        core::hint::spin_loop();
+    }
+}
+
+fn calibrate_hpet(hpet: &Hpet) -> Option<u64> {
+    let target = CALIBRATION_MS * 1_000_000_000_000 / hpet.period_fs;
+    let (cycles, counts) = measure(|| hpet.read(), hpet.bits(), target)?;
+    let ns = (counts as u128 * hpet.period_fs as u128 / FS_PER_NS as u128) as u64;
+    Some((cycles as u128 * NS_PER_SEC as u128 / ns.max(1) as u128) as u64)
+}
+
+fn calibrate_pm(pm: &PmTimer) -> Option<u64> {
+    let target = PM_TIMER_HZ * CALIBRATION_MS / 1000;
+    let (cycles, counts) = measure(|| pm.read(), pm.bits, target)?;
+    Some((cycles as u128 * PM_TIMER_HZ as u128 / counts.max(1) as u128) as u64)
+}
+
+/// PIT channel 2 in one-shot mode, gated through port 0x61 with the
+/// speaker off; OUT2 (bit 5) goes high when the count runs out.
+fn calibrate_pit() -> Option<u64> {
+    let latch = PIT_HZ * CALIBRATION_MS / 1000;
+    let cycles = unsafe {
+        let gate = inb(0x61);
+        outb(0x61, (gate & !0x02) | 0x01);
+        outb(0x43, 0xB0);
+        outb(0x42, (latch & 0xFF) as u8);
+        outb(0x42, (latch >> 8) as u8);
+        let tsc_start = crate::hal::rdtsc();
+        let mut cycles = None;
+        loop {
+            let elapsed = crate::hal::rdtsc().wrapping_sub(tsc_start);
+            if inb(0x61) & 0x20 != 0 {
+                cycles = Some(elapsed);
+                break;
+            }
+            if elapsed > CALIBRATION_SPIN_LIMIT {
+                break;
+            }
+        }
+        outb(0x61, gate);
+        cycles?
+    };
+    Some(cycles.saturating_mul(1000) / CALIBRATION_MS)
+}
+
+fn calibrate_stall() -> Option<u64> {
+    if !crate::runtime::runtime_uefi_active() {
+        return None;
+    }
+    let start = crate::hal::rdtsc();
+    uefi::boot::stall((CALIBRATION_MS * 1000) as usize);
+    Some(crate::hal::rdtsc().wrapping_sub(start).saturating_mul(1000) / CALIBRATION_MS)
+}
+
+/// Probe, calibrate and switch the clock over. Runs once at boot; later
+/// calls are no-ops.
+pub fn init() {
+    if SELECTED.is_set() {
+        return;
+    }
+    let hpet = Hpet::probe();
+    let pm = PmTimer::probe();
+    let tsc_invariant = tsc_invariant();
+
+    // Anything under 1 MHz is a failed measurement, not a real TSC.
+    let plausible = |reference: Reference, hz: Option<u64>| hz.filter(|hz| *hz >= 1_000_000).map(|hz| (reference, hz));
+    let measured = plausible(Reference::Hpet, hpet.as_ref().and_then(calibrate_hpet))
+        .or_else(|| plausible(Reference::AcpiPm, pm.as_ref().and_then(calibrate_pm)))
+        .or_else(|| plausible(Reference::Pit, calibrate_pit()))
+        .or_else(|| plausible(Reference::FirmwareStall, calibrate_stall()));
+    let reference = measured.map(|(reference, _)| reference);
+    let tsc_hz = measured.map(|(_, hz)| hz).unwrap_or(0);
+    TSC_HZ.store(tsc_hz, Ordering::Release);
+
+    let (kind, mult) = if tsc_hz != 0 && tsc_invariant {
+        (Kind::Tsc, mult_for_hz(tsc_hz))
+    } else if let Some(h) = hpet {
+        (Kind::Hpet, mult_for_hz(h.hz()))
+    } else if pm.is_some() {
+        (Kind::AcpiPm, mult_for_hz(PM_TIMER_HZ))
+    } else if tsc_hz != 0 {
+        (Kind::Tsc, mult_for_hz(tsc_hz))
+    } else {
+        (Kind::Ticks, 0)
+    };
+
+    let mut selected = Selected {
+        kind,
+        hpet,
+        pm,
+        mult,
+        base_count: 0,
+        base_ns: ticks_ns(),
+        tsc_invariant,
+        reference,
+    };
+    selected.base_count = selected.count();
+    let _ = SELECTED.set(selected);
+    crate::klog::log(
+        crate::klog::Level::Info,
+        alloc::format!(
+            "clocksource: {} (tsc {} MHz, calibrado con {})",
+            kind.name(),
+            tsc_hz / 1_000_000,
+            reference.map(Reference::name).unwrap_or("nada")
+        )
+        .as_str(),
+    );
+}
+
+/// Nanoseconds since boot. Never goes backwards, including across `init`.
+pub fn monotonic_ns() -> u64 {
+    match SELECTED.get() {
+        Some(selected) => selected.now_ns(),
+        None => ticks_ns(),
+    }
+}
+
+pub fn current() -> Kind {
+    SELECTED.get().map(|s| s.kind).unwrap_or(Kind::Ticks)
+}
+
+/// Measured TSC frequency, or 0 before calibration or when it failed.
+pub fn tsc_hz() -> u64 {
+    TSC_HZ.load(Ordering::Acquire)
+}
+
+pub fn status_lines() -> Vec<String> {
+    let mut out = Vec::new();
+    let Some(selected) = SELECTED.get() else {
+        out.push(String::from("clocksource: sin inicializar (ticks)"));
+        return out;
+    };
+    out.push(alloc::format!("Fuente actual: {}", selected.kind.name()));
+    let tsc_hz = tsc_hz();
+    out.push(alloc::format!(
+        "  tsc     {}.{:03} MHz{} calibrado con {}",
+        tsc_hz / 1_000_000,
+        (tsc_hz / 1_000) % 1_000,
+        if selected.tsc_invariant { " invariante," } else { "," },
+        selected.reference.map(Reference::name).unwrap_or("nada")
+    ));
+    match selected.hpet {
+        Some(h) => out.push(alloc::format!(
+            "  hpet    {}.{:03} MHz, {} bits, 0x{:X}",
+            h.hz() / 1_000_000,
+            (h.hz() / 1_000) % 1_000,
+            h.bits(),
+            h.base
+        )),
+        None => out.push(String::from("  hpet    no disponible")),
+    }
+    match selected.pm {
+        Some(p) => out.push(alloc::format!(
+            "  acpi_pm 3.580 MHz, {} bits, puerto 0x{:X}",
+            p.bits,
+            p.port
+        )),
+        None => out.push(String::from("  acpi_pm no disponible")),
+    }
+    out.push(alloc::format!("Monotonico: {} ns", monotonic_ns()));
+    out
+}
+
+/// `clocksource`: the selected source and what else the machine offers.
+pub fn command_lines(_args: &str) -> Vec<String> {
+    status_lines()
+}
+
+crate::selftest::kernel_tests! {
+    "clocksource";
+
+    fn scaling_matches_frequency() {
+        // The multiplier rounds down, so one second of counts may land a
+        // nanosecond short.
+        let tsc = scale(3_000_000_000, mult_for_hz(3_000_000_000));
+        crate::selftest::ensure(NS_PER_SEC - tsc <= 1, "3 GHz")?;
+        let pm = scale(PM_TIMER_HZ, mult_for_hz(PM_TIMER_HZ));
+        crate::selftest::ensure(NS_PER_SEC - pm <= 1, "pm timer")
+    }
+
+    fn clock_is_monotonic() {
+        let mut last = monotonic_ns();
+        for _ in 0..1000 {
+            let now = monotonic_ns();
+            crate::selftest::ensure(now >= last, "retrocede")?;
+            last = now;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+//! Boot options: `\REDUXOS\CMDLINE.TXT` on the boot volume followed by the
+//! UEFI load options, read by `efi_main` before any subsystem starts.
+//!
+//! Words are separated by blanks or newlines; `#` starts a comment. A word
+//! in the load options overrides the same switch in the file, so a one-off
+//! boot entry can undo what the file sets.
+//!
+//! - `loglevel=<level>`: console threshold (`err`, `warn`, `info`, `debug`,
+//!   see `klog::Level::parse`); `debug` also skips the splash so every boot
+//!   line stays on screen.
+//! - `nogui` / `gui`: stay in the text shell instead of starting the desktop.
+//! - `net=off|dhcp|static:<ip>/<prefix>,<gateway>`: skip the network stack or
+//!   force its IPv4 mode for this boot.
+//! - `safe-mode`: boot with the minimal driver set, see `safemode`.
+//!
+//! `recovery`, `testharness` and `selftest` are read by their own modules
+//! from `text()`, so they work from the file too.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::klog::Level;
+use crate::spinlock::SpinLock;
+
+pub const FILE_PATH: &str = "\\REDUXOS\\CMDLINE.TXT";
+
+/// Words other modules look for in `text()`.
+const PASSTHROUGH: [&str; 3] = ["recovery", "testharness", "selftest"];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NetMode {
+    Off,
+    Dhcp,
+    Static { ip: [u8; 4], prefix: u8, gateway: [u8; 4] },
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct BootOptions {
+    pub log_level: Option<Level>,
+    pub nogui: bool,
+    pub net: Option<NetMode>,
+    pub safe_mode: bool,
+    /// Every word, file first, for the modules that match their own.
+    pub words: Vec<String>,
+    /// Words that were not understood, with the reason.
+    pub errors: Vec<String>,
+}
+
+struct State {
+    options: BootOptions,
+    from_file: bool,
+}
+
+static STATE: SpinLock<State> = SpinLock::new(State {
+    options: BootOptions {
+        log_level: None,
+        nogui: false,
+        net: None,
+        safe_mode: false,
+        words: Vec::new(),
+        errors: Vec::new(),
+    },
+    from_file: false,
+});
+
+fn strip_comments(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .flat_map(|line| line.split('#').next().unwrap_or("").split_whitespace())
+}
+
+fn parse_ipv4(text: &str) -> Option<[u8; 4]> {
+    let mut out = [0u8; 4];
+    let mut parts = text.split('.');
+    for octet in out.iter_mut() {
+        *octet = parts.next()?.parse().ok()?;
+    }
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(out)
+}
+
+pub fn parse_net(value: &str) -> Option<NetMode> {
+    match value {
+        "off" | "none" => return Some(NetMode::Off),
+        "dhcp" => return Some(NetMode::Dhcp),
+        _ => {}
+    }
+    let spec = value.strip_prefix("static:")?;
+    let (address, gateway) = spec.split_once(',')?;
+    let (ip, prefix) = address.split_once('/')?;
+    let prefix: u8 = prefix.parse().ok()?;
+    if prefix == 0 || prefix > 32 {
+        return None;
+    }
+    Some(NetMode::Static {
+        ip: parse_ipv4(ip)?,
+        prefix,
+        gateway: parse_ipv4(gateway)?,
+    })
+}
+
+/// Applies every word of `text` in order over `options`.
+fn apply(options: &mut BootOptions, text: &str) {
+    for word in strip_comments(text) {
+        options.words.push(String::from(word));
+        let lower = word.to_ascii_lowercase();
+        let (key, value) = match lower.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (lower.as_str(), None),
+        };
+        match (key, value) {
+            ("loglevel", Some(value)) => match Level::parse(value) {
+                Some(level) => options.log_level = Some(level),
+                None => options.errors.push(alloc::format!("{}: nivel desconocido", word)),
+            },
+            ("nogui", None) => options.nogui = true,
+            ("gui", None) => options.nogui = false,
+            ("net", Some(value)) => match parse_net(value) {
+                Some(mode) => options.net = Some(mode),
+                None => options
+                    .errors
+                    .push(alloc::format!("{}: usa off, dhcp o static:ip/prefijo,gateway", word)),
+            },
+            ("safe-mode" | "safemode", None) => options.safe_mode = true,
+            _ if PASSTHROUGH.contains(&key) => {}
+            _ => options.errors.push(alloc::format!("{}: opcion desconocida", word)),
+        }
+    }
+}
+
+pub fn parse(file: Option<&str>, load_options: Option<&str>) -> BootOptions {
+    let mut options = BootOptions::default();
+    if let Some(file) = file {
+        apply(&mut options, file);
+    }
+    if let Some(load_options) = load_options {
+        apply(&mut options, load_options);
+    }
+    options
+}
+
+/// Reads both sources and keeps the result for `options()`. Called once,
+/// before the subsystems the switches steer.
+pub fn load(boot_device: Option<uefi::Handle>, load_options: Option<&str>) {
+    let file = boot_device.and_then(|handle| {
+        let path = uefi::CString16::try_from(FILE_PATH).ok()?;
+        crate::read_file_from_fs_handle(handle, &path)
+    });
+    let file_text = file.as_deref().map(String::from_utf8_lossy);
+    let options = parse(file_text.as_deref(), load_options);
+    if let Some(level) = options.log_level {
+        crate::klog::set_console_level(level);
+    }
+    for error in options.errors.iter() {
+        crate::println(alloc::format!("cmdline: {}", error).as_str());
+    }
+    let mut state = STATE.lock();
+    state.options = options;
+    state.from_file = file.is_some();
+}
+
+pub fn options() -> BootOptions {
+    STATE.lock().options.clone()
+}
+
+/// All words, space-separated, in the shape the load options have.
+pub fn text() -> String {
+    STATE.lock().options.words.join(" ")
+}
+
+pub fn verbose_boot() -> bool {
+    STATE
+        .lock()
+        .options
+        .log_level
+        .is_some_and(|level| level >= Level::Debug)
+}
+
+fn ipv4_text(ip: [u8; 4]) -> String {
+    alloc::format!("{}.{}.{}.{}", ip[0], ip[1], ip[2], ip[3])
+}
+
+pub fn command_lines() -> Vec<String> {
+    let state = STATE.lock();
+    let options = &state.options;
+    let mut out = alloc::vec![
+        alloc::format!(
+            "Opciones de arranque: {}",
+            if options.words.is_empty() {
+                String::from("(ninguna)")
+            } else {
+                options.words.join(" ")
+            }
+        ),
+        alloc::format!("{}: {}", FILE_PATH, if state.from_file { "leido" } else { "no existe" }),
+        alloc::format!(
+            "loglevel={}  gui={}  safe-mode={}",
+            options.log_level.map(Level::as_str).unwrap_or("info"),
+            if options.nogui { "no" } else { "si" },
+            if options.safe_mode { "si" } else { "no" }
+        ),
+        alloc::format!(
+            "net={}",
+            match options.net {
+                None => String::from("(config)"),
+                Some(NetMode::Off) => String::from("off"),
+                Some(NetMode::Dhcp) => String::from("dhcp"),
+                Some(NetMode::Static { ip, prefix, gateway }) => {
+                    alloc::format!("static:{}/{},{}", ipv4_text(ip), prefix, ipv4_text(gateway))
+                }
+            }
+        ),
+    ];
+    for error in options.errors.iter() {
+        out.push(alloc::format!("  ignorado: {}", error));
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "cmdline";
+
+    fn load_options_override_file() {
+        let file = "# modo diagnostico\nloglevel=debug nogui\nnet=static:10.0.2.15/24,10.0.2.2  # qemu\n";
+        let options = parse(Some(file), Some("gui recovery loglevel=warn"));
+        crate::selftest::ensure(options.log_level == Some(Level::Warning), "loglevel de las opciones UEFI")?;
+        crate::selftest::ensure(!options.nogui, "gui deshace nogui")?;
+        crate::selftest::ensure_eq(
+            options.net,
+            Some(NetMode::Static {
+                ip: [10, 0, 2, 15],
+                prefix: 24,
+                gateway: [10, 0, 2, 2],
+            }),
+            "red fija del archivo",
+        )?;
+        crate::selftest::ensure_eq(options.errors.len(), 0, "sin errores")?;
+        crate::selftest::ensure_eq(options.words.len(), 6, "palabras sin comentarios")
+    }
+
+    fn bad_switches_are_reported() {
+        let options = parse(None, Some("net=static:10.0.2.15/33,10.0.2.2 loglevel=loud splash safe-mode"));
+        crate::selftest::ensure_eq(options.net, None, "prefijo invalido")?;
+        crate::selftest::ensure_eq(options.log_level, None, "nivel invalido")?;
+        crate::selftest::ensure(options.safe_mode, "safe-mode")?;
+        crate::selftest::ensure_eq(options.errors.len(), 3, "tres avisos")?;
+        crate::selftest::ensure_eq(parse_net("off"), Some(NetMode::Off), "net=off")
+    }
+}
@@ -0,0 +1,323 @@
+//! DEFLATE, zlib (RFC 1950) and gzip (RFC 1952) encoding on top of
+//! miniz_oxide, plus a checked gzip decoder.
+//!
+//! The HTTP client and the installer only ever inflated; this module is the
+//! write side used for package archives, rotated logs (`log save`) and
+//! `Content-Encoding: gzip` request bodies, and backs the `gzip` / `gunzip`
+//! shell commands.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use miniz_oxide::deflate::{compress_to_vec, compress_to_vec_zlib};
+use miniz_oxide::inflate::{decompress_to_vec_with_limit, decompress_to_vec_zlib_with_limit};
+
+pub const DEFAULT_LEVEL: u8 = 6;
+pub const MAX_LEVEL: u8 = 9;
+
+/// Upper bound for `gunzip` output, so a small bomb cannot exhaust the heap.
+pub const GUNZIP_MAX_OUTPUT: usize = 64 * 1024 * 1024;
+
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const GZIP_METHOD_DEFLATE: u8 = 8;
+const GZIP_FLAG_HCRC: u8 = 0x02;
+const GZIP_FLAG_EXTRA: u8 = 0x04;
+const GZIP_FLAG_NAME: u8 = 0x08;
+const GZIP_FLAG_COMMENT: u8 = 0x10;
+/// OS byte of the header; 255 is "unknown".
+const GZIP_OS_UNKNOWN: u8 = 255;
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Continue a CRC-32 (IEEE, as used by gzip and zip). Start from 0.
+pub fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in data {
+        crc = CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+/// Raw DEFLATE stream. `level` is clamped to 0..=9.
+pub fn deflate(data: &[u8], level: u8) -> Vec<u8> {
+    compress_to_vec(data, level.min(MAX_LEVEL))
+}
+
+/// zlib-wrapped DEFLATE stream (`Content-Encoding: deflate`).
+pub fn zlib_compress(data: &[u8], level: u8) -> Vec<u8> {
+    compress_to_vec_zlib(data, level.min(MAX_LEVEL))
+}
+
+pub fn zlib_decompress(raw: &[u8], max_output: usize) -> Result<Vec<u8>, &'static str> {
+    decompress_to_vec_zlib_with_limit(raw, max_output).map_err(|_| "zlib invalido o demasiado grande.")
+}
+
+/// Single-member gzip file. `name` goes in the FNAME field (Latin-1 per the
+/// RFC, so anything outside ASCII is dropped); `mtime` is Unix seconds, 0
+/// when unknown.
+pub fn gzip_compress(data: &[u8], level: u8, name: Option<&str>, mtime: u32) -> Vec<u8> {
+    let level = level.min(MAX_LEVEL);
+    let body = compress_to_vec(data, level);
+    let name: Vec<u8> = name
+        .unwrap_or("")
+        .bytes()
+        .filter(|b| b.is_ascii() && *b != 0 && *b != b'/' && *b != b'\\')
+        .collect();
+
+    let mut out = Vec::with_capacity(body.len() + name.len() + 19);
+    out.extend_from_slice(&GZIP_MAGIC);
+    out.push(GZIP_METHOD_DEFLATE);
+    out.push(if name.is_empty() { 0 } else { GZIP_FLAG_NAME });
+    out.extend_from_slice(&mtime.to_le_bytes());
+    // XFL: 2 = best compression, 4 = fastest.
+    out.push(match level {
+        9 => 2,
+        0 | 1 => 4,
+        _ => 0,
+    });
+    out.push(GZIP_OS_UNKNOWN);
+    if !name.is_empty() {
+        out.extend_from_slice(name.as_slice());
+        out.push(0);
+    }
+    out.extend_from_slice(body.as_slice());
+    out.extend_from_slice(&crc32(data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+pub fn is_gzip(raw: &[u8]) -> bool {
+    raw.len() >= 3 && raw[..2] == GZIP_MAGIC && raw[2] == GZIP_METHOD_DEFLATE
+}
+
+/// Fields of a gzip member header.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GzipHeader {
+    pub name: Option<String>,
+    pub mtime: u32,
+    /// Offset of the DEFLATE data.
+    pub data_offset: usize,
+}
+
+fn skip_zero_terminated(raw: &[u8], from: usize) -> Option<usize> {
+    let len = raw.get(from..)?.iter().position(|&b| b == 0)?;
+    Some(from + len + 1)
+}
+
+pub fn parse_gzip_header(raw: &[u8]) -> Result<GzipHeader, &'static str> {
+    if raw.len() < 18 || !is_gzip(raw) {
+        return Err("no es un archivo gzip.");
+    }
+    let flags = raw[3];
+    let mtime = u32::from_le_bytes([raw[4], raw[5], raw[6], raw[7]]);
+    let mut pos = 10usize;
+    if flags & GZIP_FLAG_EXTRA != 0 {
+        let xlen = raw.get(pos..pos + 2).ok_or("cabecera gzip truncada.")?;
+        pos += 2 + u16::from_le_bytes([xlen[0], xlen[1]]) as usize;
+    }
+    let mut name = None;
+    if flags & GZIP_FLAG_NAME != 0 {
+        let end = skip_zero_terminated(raw, pos).ok_or("cabecera gzip truncada.")?;
+        // FNAME is Latin-1; map bytes straight to code points.
+        name = Some(raw[pos..end - 1].iter().map(|&b| b as char).collect());
+        pos = end;
+    }
+    if flags & GZIP_FLAG_COMMENT != 0 {
+        pos = skip_zero_terminated(raw, pos).ok_or("cabecera gzip truncada.")?;
+    }
+    if flags & GZIP_FLAG_HCRC != 0 {
+        pos += 2;
+    }
+    if pos + 8 > raw.len() {
+        return Err("cabecera gzip truncada.");
+    }
+    Ok(GzipHeader {
+        name,
+        mtime,
+        data_offset: pos,
+    })
+}
+
+/// Inflate a single-member gzip file and check its CRC-32 and size trailer.
+pub fn gzip_decompress(raw: &[u8], max_output: usize) -> Result<(GzipHeader, Vec<u8>), &'static str> {
+    let header = parse_gzip_header(raw)?;
+    let trailer = &raw[raw.len() - 8..];
+    let expected_crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let expected_len = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+    if expected_len as usize > max_output {
+        return Err("gzip demasiado grande.");
+    }
+    let data = decompress_to_vec_with_limit(&raw[header.data_offset..raw.len() - 8], max_output)
+        .map_err(|_| "datos DEFLATE invalidos.")?;
+    if data.len() as u32 != expected_len || crc32(data.as_slice()) != expected_crc {
+        return Err("CRC o tamano de gzip no coincide.");
+    }
+    Ok((header, data))
+}
+
+/// Default `gzip` output name. FAT32 keeps 8.3 names, so `NOTES.TXT` becomes
+/// `NOTES.GZ` and the original name travels in the header.
+fn gzip_output_name(input: &str) -> String {
+    let stem = match input.rfind('.') {
+        Some(dot) if dot > 0 => &input[..dot],
+        _ => input,
+    };
+    alloc::format!("{}.GZ", stem)
+}
+
+fn gunzip_output_name(input: &str, header: &GzipHeader) -> String {
+    if let Some(name) = header.name.as_deref().and_then(|n| n.rsplit(['/', '\\']).next()) {
+        if !name.is_empty() && name.is_ascii() {
+            return String::from(name);
+        }
+    }
+    let lower = input.to_ascii_lowercase();
+    for ext in [".gz", ".tgz", ".z"] {
+        if lower.ends_with(ext) && lower.len() > ext.len() {
+            let stem = &input[..input.len() - ext.len()];
+            return if ext == ".tgz" { alloc::format!("{}.TAR", stem) } else { String::from(stem) };
+        }
+    }
+    alloc::format!("{}.OUT", input)
+}
+
+fn percent(part: usize, whole: usize) -> usize {
+    if whole == 0 {
+        100
+    } else {
+        part.saturating_mul(100) / whole
+    }
+}
+
+/// Shared implementation of `gzip [-1..-9] <archivo> [salida]` and
+/// `gunzip <archivo> [salida]` on files of the FAT directory `dir_cluster`.
+pub fn command_lines(verb: &str, args: &str, dir_cluster: u32) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut level = DEFAULT_LEVEL;
+    let mut files = Vec::new();
+    for arg in args.split_whitespace() {
+        match arg.strip_prefix('-').and_then(|l| l.parse::<u8>().ok()) {
+            Some(l) if verb == "gzip" && (1..=MAX_LEVEL).contains(&l) => level = l,
+            _ => files.push(arg),
+        }
+    }
+    let (Some(input), None) = (files.first().copied(), files.get(2)) else {
+        out.push(String::from(if verb == "gzip" {
+            "Uso: gzip [-1..-9] <archivo> [salida]"
+        } else {
+            "Uso: gunzip <archivo.gz> [salida]"
+        }));
+        return out;
+    };
+
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let raw = match fat.read_file_in_dir(dir_cluster, input) {
+        Ok(raw) => raw,
+        Err(e) => {
+            out.push(alloc::format!("{}: {}: {}", verb, input, e));
+            return out;
+        }
+    };
+
+    let (target, data) = if verb == "gzip" {
+        let mtime = (crate::timer::wall_clock_unix_millis() / 1000).max(0) as u32;
+        let packed = gzip_compress(raw.as_slice(), level, Some(input), mtime);
+        let target = files.get(1).map(|s| String::from(*s)).unwrap_or_else(|| gzip_output_name(input));
+        (target, packed)
+    } else {
+        match gzip_decompress(raw.as_slice(), GUNZIP_MAX_OUTPUT) {
+            Ok((header, data)) => {
+                let target = files
+                    .get(1)
+                    .map(|s| String::from(*s))
+                    .unwrap_or_else(|| gunzip_output_name(input, &header));
+                (target, data)
+            }
+            Err(e) => {
+                out.push(alloc::format!("gunzip: {}: {}", input, e));
+                return out;
+            }
+        }
+    };
+
+    if target.eq_ignore_ascii_case(input) {
+        out.push(alloc::format!("{}: la salida sobrescribiria {}.", verb, input));
+        return out;
+    }
+    match fat.write_text_file_in_dir(dir_cluster, target.as_str(), data.as_slice()) {
+        Ok(()) => {
+            let (plain, packed) = if verb == "gzip" { (raw.len(), data.len()) } else { (data.len(), raw.len()) };
+            out.push(alloc::format!(
+                "{} -> {} ({} -> {} bytes, {}%)",
+                input,
+                target,
+                raw.len(),
+                data.len(),
+                percent(packed, plain)
+            ));
+        }
+        Err(e) => out.push(alloc::format!("{}: {}: {}", verb, target, e)),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "compress";
+
+    fn crc32_check_value() {
+        crate::selftest::ensure_eq(crc32(b"123456789"), 0xCBF4_3926, "crc32")?;
+        let split = crc32_update(crc32(b"1234"), b"56789");
+        crate::selftest::ensure_eq(split, 0xCBF4_3926, "crc32 incremental")
+    }
+
+    fn gzip_roundtrip() {
+        let mut data = Vec::new();
+        for i in 0..4096u32 {
+            data.extend_from_slice(alloc::format!("linea {} {}\n", i, i % 7).as_bytes());
+        }
+        let packed = gzip_compress(data.as_slice(), DEFAULT_LEVEL, Some("LOG.TXT"), 1_700_000_000);
+        crate::selftest::ensure(packed.len() < data.len() / 2, "gzip comprime")?;
+        let (header, plain) = gzip_decompress(packed.as_slice(), data.len())?;
+        crate::selftest::ensure_eq(header.name.as_deref(), Some("LOG.TXT"), "fname")?;
+        crate::selftest::ensure_eq(header.mtime, 1_700_000_000, "mtime")?;
+        crate::selftest::ensure(plain == data, "gzip roundtrip")
+    }
+
+    fn gzip_rejects_corruption() {
+        let mut packed = gzip_compress(b"hola mundo", 9, None, 0);
+        let crc_at = packed.len() - 8;
+        packed[crc_at] ^= 0x01;
+        crate::selftest::ensure(gzip_decompress(packed.as_slice(), 1024).is_err(), "crc erroneo")?;
+        crate::selftest::ensure(gzip_decompress(b"\x1f\x8b", 1024).is_err(), "gzip truncado")
+    }
+
+    fn zlib_roundtrip() {
+        let data = b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+        let packed = zlib_compress(data, DEFAULT_LEVEL);
+        let plain = zlib_decompress(packed.as_slice(), 1024)?;
+        crate::selftest::ensure(plain.as_slice() == &data[..], "zlib roundtrip")?;
+        let raw = deflate(data, 1);
+        let plain = miniz_oxide::inflate::decompress_to_vec(raw.as_slice()).map_err(|_| String::from("inflate"))?;
+        crate::selftest::ensure(plain.as_slice() == &data[..], "deflate roundtrip")
+    }
+}
@@ -0,0 +1,889 @@
+//! Persistent system configuration registry.
+//!
+//! Typed values under dotted keys ("desktop.background", "system.hostname")
+//! live in memory and are persisted in `\REDUXOS` on the boot volume:
+//!
+//! - `CONFIG.BIN`: full snapshot, CRC-checked.
+//! - `CONFIG.JNL`: changes made since the snapshot, one CRC-checked record
+//!   each. Every `set` rewrites only this file; replay stops at the first
+//!   damaged record, so an interrupted write loses at most that change.
+//! - `CONFIG.OLD`: the previous snapshot, kept while the journal is folded
+//!   into a new `CONFIG.BIN` in case that write is cut short.
+//!
+//! Kernel modules read with `get_*` and subscribe with `watch`; ring 3 uses
+//! `SYS_CONFIG_GET` / `SYS_CONFIG_SET` / `SYS_CONFIG_WATCH`.
+//!
+//! When something other than this module writes the files (an editor, a
+//! copy from another drive), `crate::fswatch` reports it and the next access
+//! reloads them; the keys that differ go through the watchers like a `set`.
+//!
+//! The values loaded from disk are measured into TPM PCR 12 (`tpm`).
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+use crate::spinlock::SpinLock;
+
+const CONFIG_DIR: &str = "REDUXOS";
+const CONFIG_FILE: &str = "CONFIG.BIN";
+const CONFIG_OLD_FILE: &str = "CONFIG.OLD";
+const CONFIG_JOURNAL_FILE: &str = "CONFIG.JNL";
+const SNAPSHOT_MAGIC: &[u8; 4] = b"RXCF";
+const SNAPSHOT_VERSION: u8 = 1;
+const JOURNAL_MAGIC: u8 = 0xC5;
+const OP_SET: u8 = 0;
+const OP_UNSET: u8 = 1;
+/// Journal records kept before they are folded into a new snapshot.
+const COMPACT_AFTER: usize = 32;
+pub const KEY_MAX_BYTES: usize = 64;
+pub const VALUE_MAX_BYTES: usize = 256;
+const MAX_WATCHERS: usize = 32;
+
+const TAG_BOOL: u8 = 1;
+const TAG_INT: u8 = 2;
+const TAG_COLOR: u8 = 3;
+const TAG_STR: u8 = 4;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ConfigValue {
+    Bool(bool),
+    Int(i64),
+    /// 0xRRGGBB.
+    Color(u32),
+    Str(String),
+}
+
+impl ConfigValue {
+    /// Typed value from user text: `true`/`false`, `#RRGGBB`, decimal or
+    /// `0x` integers; anything else is a string.
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        match text {
+            "true" | "on" => return ConfigValue::Bool(true),
+            "false" | "off" => return ConfigValue::Bool(false),
+            _ => {}
+        }
+        if let Some(hex) = text.strip_prefix('#') {
+            if hex.len() == 6 {
+                if let Ok(rgb) = u32::from_str_radix(hex, 16) {
+                    return ConfigValue::Color(rgb);
+                }
+            }
+        }
+        if let Some(hex) = text.strip_prefix("0x") {
+            if let Ok(v) = i64::from_str_radix(hex, 16) {
+                return ConfigValue::Int(v);
+            }
+        }
+        if let Ok(v) = text.parse::<i64>() {
+            return ConfigValue::Int(v);
+        }
+        ConfigValue::Str(String::from(text))
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ConfigValue::Bool(_) => "bool",
+            ConfigValue::Int(_) => "int",
+            ConfigValue::Color(_) => "color",
+            ConfigValue::Str(_) => "str",
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        match self {
+            ConfigValue::Bool(v) => String::from(if *v { "true" } else { "false" }),
+            ConfigValue::Int(v) => alloc::format!("{}", v),
+            ConfigValue::Color(v) => alloc::format!("#{:06X}", v & 0xFF_FFFF),
+            ConfigValue::Str(v) => v.clone(),
+        }
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            ConfigValue::Bool(v) => {
+                out.push(TAG_BOOL);
+                out.push(*v as u8);
+            }
+            ConfigValue::Int(v) => {
+                out.push(TAG_INT);
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            ConfigValue::Color(v) => {
+                out.push(TAG_COLOR);
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            ConfigValue::Str(v) => {
+                out.push(TAG_STR);
+                out.extend_from_slice(&(v.len() as u16).to_le_bytes());
+                out.extend_from_slice(v.as_bytes());
+            }
+        }
+    }
+
+    fn decode(cur: &mut Cursor) -> Option<Self> {
+        match cur.u8()? {
+            TAG_BOOL => Some(ConfigValue::Bool(cur.u8()? != 0)),
+            TAG_INT => Some(ConfigValue::Int(cur.u64()? as i64)),
+            TAG_COLOR => Some(ConfigValue::Color(cur.u32()?)),
+            TAG_STR => {
+                let len = cur.u16()? as usize;
+                let text = core::str::from_utf8(cur.bytes(len)?).ok()?;
+                Some(ConfigValue::Str(String::from(text)))
+            }
+            _ => None,
+        }
+    }
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(len)?;
+        let out = self.data.get(self.pos..end)?;
+        self.pos = end;
+        Some(out)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.bytes(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.bytes(8)?.try_into().ok()?))
+    }
+
+    fn key(&mut self) -> Option<String> {
+        let len = self.u8()? as usize;
+        let key = core::str::from_utf8(self.bytes(len)?).ok()?;
+        if valid_key(key) {
+            Some(String::from(key))
+        } else {
+            None
+        }
+    }
+}
+
+/// Lower-case dotted path: `[a-z0-9_-]` segments separated by single dots.
+pub fn valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= KEY_MAX_BYTES
+        && key.split('.').all(|seg| {
+            !seg.is_empty()
+                && seg
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_' || b == b'-')
+        })
+}
+
+fn valid_value(value: &ConfigValue) -> bool {
+    match value {
+        ConfigValue::Str(s) => s.len() <= VALUE_MAX_BYTES,
+        _ => true,
+    }
+}
+
+fn push_key(out: &mut Vec<u8>, key: &str) {
+    out.push(key.len() as u8);
+    out.extend_from_slice(key.as_bytes());
+}
+
+/// In-memory registry plus the encoded journal of changes since the last
+/// snapshot.
+pub struct Registry {
+    values: BTreeMap<String, ConfigValue>,
+    /// Sequence number of the last applied change.
+    seq: u64,
+    journal: Vec<u8>,
+    journal_records: usize,
+    /// Last change per key (including removed keys), for `revision`.
+    changed: BTreeMap<String, u64>,
+}
+
+impl Registry {
+    pub const fn new() -> Self {
+        Self {
+            values: BTreeMap::new(),
+            seq: 0,
+            journal: Vec::new(),
+            journal_records: 0,
+            changed: BTreeMap::new(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ConfigValue> {
+        self.values.get(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Entries whose key starts with `prefix`, in key order.
+    pub fn list(&self, prefix: &str) -> Vec<(String, ConfigValue)> {
+        self.values
+            .range::<str, _>((core::ops::Bound::Included(prefix), core::ops::Bound::Unbounded))
+            .take_while(|(k, _)| k.starts_with(prefix))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    /// Sequence number of the newest change under `prefix`, 0 if none.
+    pub fn revision(&self, prefix: &str) -> u64 {
+        self.changed
+            .iter()
+            .filter(|(k, _)| k.starts_with(prefix))
+            .map(|(_, &seq)| seq)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn apply(&mut self, seq: u64, key: &str, value: Option<ConfigValue>) {
+        match value {
+            Some(v) => {
+                self.values.insert(String::from(key), v);
+            }
+            None => {
+                self.values.remove(key);
+            }
+        }
+        self.changed.insert(String::from(key), seq);
+        self.seq = seq;
+    }
+
+    fn record(&mut self, key: &str, value: Option<&ConfigValue>) {
+        let mut body = Vec::new();
+        body.extend_from_slice(&self.seq.to_le_bytes());
+        match value {
+            Some(v) => {
+                body.push(OP_SET);
+                push_key(&mut body, key);
+                v.encode(&mut body);
+            }
+            None => {
+                body.push(OP_UNSET);
+                push_key(&mut body, key);
+            }
+        }
+        self.journal.push(JOURNAL_MAGIC);
+        self.journal.extend_from_slice(&(body.len() as u16).to_le_bytes());
+        self.journal.extend_from_slice(&body);
+        self.journal
+            .extend_from_slice(&crate::compress::crc32(&body).to_le_bytes());
+        self.journal_records += 1;
+    }
+
+    /// Store `value`. Returns false when the key or value is invalid or the
+    /// value is unchanged.
+    pub fn set(&mut self, key: &str, value: ConfigValue) -> bool {
+        if !valid_key(key) || !valid_value(&value) || self.values.get(key) == Some(&value) {
+            return false;
+        }
+        self.apply(self.seq + 1, key, Some(value.clone()));
+        self.record(key, Some(&value));
+        true
+    }
+
+    pub fn unset(&mut self, key: &str) -> bool {
+        if !self.values.contains_key(key) {
+            return false;
+        }
+        self.apply(self.seq + 1, key, None);
+        self.record(key, None);
+        true
+    }
+
+    pub fn encode_snapshot(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(SNAPSHOT_MAGIC);
+        out.push(SNAPSHOT_VERSION);
+        out.extend_from_slice(&self.seq.to_le_bytes());
+        out.extend_from_slice(&(self.values.len() as u32).to_le_bytes());
+        for (key, value) in self.values.iter() {
+            push_key(&mut out, key);
+            value.encode(&mut out);
+        }
+        let crc = crate::compress::crc32(&out);
+        out.extend_from_slice(&crc.to_le_bytes());
+        out
+    }
+
+    /// Replace the contents with a snapshot. Nothing changes on error.
+    pub fn load_snapshot(&mut self, raw: &[u8]) -> Result<(), &'static str> {
+        if raw.len() < 4 + 1 + 8 + 4 + 4 || &raw[..4] != SNAPSHOT_MAGIC {
+            return Err("config: snapshot no reconocido");
+        }
+        let (body, crc) = raw.split_at(raw.len() - 4);
+        if crate::compress::crc32(body) != u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]]) {
+            return Err("config: snapshot corrupto (CRC)");
+        }
+        let mut cur = Cursor::new(&body[4..]);
+        if cur.u8() != Some(SNAPSHOT_VERSION) {
+            return Err("config: version de snapshot no soportada");
+        }
+        let seq = cur.u64().ok_or("config: snapshot truncado")?;
+        let count = cur.u32().ok_or("config: snapshot truncado")?;
+        let mut values = BTreeMap::new();
+        for _ in 0..count {
+            let key = cur.key().ok_or("config: clave invalida")?;
+            let value = ConfigValue::decode(&mut cur).ok_or("config: valor invalido")?;
+            values.insert(key, value);
+        }
+        self.values = values;
+        self.seq = seq;
+        self.journal.clear();
+        self.journal_records = 0;
+        Ok(())
+    }
+
+    /// Apply journal records newer than the current state, stopping at the
+    /// first damaged one. Returns the keys that changed.
+    pub fn replay_journal(&mut self, raw: &[u8]) -> Vec<String> {
+        let mut keys = Vec::new();
+        let mut pos = 0usize;
+        while pos + 3 <= raw.len() && raw[pos] == JOURNAL_MAGIC {
+            let len = u16::from_le_bytes([raw[pos + 1], raw[pos + 2]]) as usize;
+            let end = pos + 3 + len + 4;
+            if end > raw.len() {
+                break;
+            }
+            let body = &raw[pos + 3..pos + 3 + len];
+            let crc = &raw[pos + 3 + len..end];
+            if crate::compress::crc32(body) != u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]]) {
+                break;
+            }
+            let mut cur = Cursor::new(body);
+            let (Some(seq), Some(op), Some(key)) = (cur.u64(), cur.u8(), cur.key()) else {
+                break;
+            };
+            let value = match op {
+                OP_SET => match ConfigValue::decode(&mut cur) {
+                    Some(v) => Some(v),
+                    None => break,
+                },
+                OP_UNSET => None,
+                _ => break,
+            };
+            // Records up to the snapshot are left over from a compaction
+            // that stopped before clearing the journal.
+            if seq > self.seq {
+                self.apply(seq, key.as_str(), value);
+                self.journal.extend_from_slice(&raw[pos..end]);
+                self.journal_records += 1;
+                keys.push(key);
+            }
+            pos = end;
+        }
+        keys
+    }
+
+    fn needs_compaction(&self) -> bool {
+        self.journal_records >= COMPACT_AFTER
+    }
+
+    fn mark_compacted(&mut self) {
+        self.journal.clear();
+        self.journal_records = 0;
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Called after a watched key changes; `None` means it was removed.
+pub type WatchFn = fn(key: &str, value: Option<&ConfigValue>);
+
+struct Watcher {
+    prefix: &'static str,
+    callback: WatchFn,
+}
+
+static REGISTRY: SpinLock<Registry> = SpinLock::new(Registry::new());
+static WATCHERS: SpinLock<Vec<Watcher>> = SpinLock::new(Vec::new());
+static LOADED: AtomicBool = AtomicBool::new(false);
+/// Set while `persist` writes, so its own changes are not reloaded.
+static WRITING: AtomicBool = AtomicBool::new(false);
+/// The files changed on disk behind our back.
+static RELOAD_PENDING: AtomicBool = AtomicBool::new(false);
+/// Volume and folder the files were loaded from.
+static LOADED_VOLUME: AtomicU64 = AtomicU64::new(0);
+static LOADED_DIR: AtomicU32 = AtomicU32::new(0);
+
+fn notify(keys: &[String]) {
+    if keys.is_empty() {
+        return;
+    }
+    let callbacks: Vec<(&'static str, WatchFn)> = WATCHERS.lock().iter().map(|w| (w.prefix, w.callback)).collect();
+    for key in keys.iter() {
+        let value = REGISTRY.lock().get(key).cloned();
+        for (prefix, callback) in callbacks.iter() {
+            if key.starts_with(prefix) {
+                callback(key.as_str(), value.as_ref());
+            }
+        }
+    }
+}
+
+/// Register `callback` for changes to keys starting with `prefix` (empty
+/// prefix: every key). Callbacks run on the thread that made the change,
+/// outside the registry lock.
+pub fn watch(prefix: &'static str, callback: WatchFn) -> bool {
+    let mut watchers = WATCHERS.lock();
+    if watchers.len() >= MAX_WATCHERS {
+        return false;
+    }
+    watchers.push(Watcher { prefix, callback });
+    true
+}
+
+fn fs_ready() -> bool {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get() };
+    fat.init_status == crate::fat32::InitStatus::Success
+}
+
+fn config_dir() -> Result<u32, &'static str> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    fat.ensure_subdirectory(fat.root_cluster, CONFIG_DIR)
+}
+
+fn read_config_file(dir: u32, name: &str) -> Option<Vec<u8>> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    fat.read_file_in_dir(dir, name).ok()
+}
+
+/// Load the registry from disk once the boot volume is mounted. Values set
+/// before that stay in memory only. Watchers are told about every loaded key.
+pub fn ensure_loaded() {
+    if LOADED.load(Ordering::Acquire) {
+        if RELOAD_PENDING.swap(false, Ordering::AcqRel) {
+            reload();
+        }
+        return;
+    }
+    if !fs_ready() {
+        return;
+    }
+    let Ok(dir) = config_dir() else {
+        return;
+    };
+    if LOADED.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let volume = unsafe { crate::fat32::GLOBAL_FAT.get() }.volume_id();
+    LOADED_VOLUME.store(volume, Ordering::Release);
+    LOADED_DIR.store(dir, Ordering::Release);
+    crate::fswatch::subscribe(on_fs_event);
+
+    let mut loaded = Registry::new();
+    let mut source = "vacio";
+    for name in [CONFIG_FILE, CONFIG_OLD_FILE] {
+        if let Some(raw) = read_config_file(dir, name) {
+            match loaded.load_snapshot(raw.as_slice()) {
+                Ok(()) => {
+                    source = name;
+                    break;
+                }
+                Err(e) => crate::klog::log(crate::klog::Level::Warning, alloc::format!("{} ({})", e, name).as_str()),
+            }
+        }
+    }
+    let replayed = match read_config_file(dir, CONFIG_JOURNAL_FILE) {
+        Some(raw) => loaded.replay_journal(raw.as_slice()).len(),
+        None => 0,
+    };
+    let mut measured = String::new();
+    for (key, value) in loaded.list("") {
+        measured.push_str(key.as_str());
+        measured.push('=');
+        measured.push_str(value.to_text().as_str());
+        measured.push('\n');
+    }
+    crate::tpm::measure(crate::tpm::PCR_CONFIG, "config", measured.as_bytes());
+
+    let keys: Vec<String> = {
+        let mut reg = REGISTRY.lock();
+        // Keep anything set in memory before the disk was available.
+        let early: Vec<(String, ConfigValue)> = reg.list("");
+        *reg = loaded;
+        for (key, value) in early {
+            reg.set(key.as_str(), value);
+        }
+        reg.list("").into_iter().map(|(k, _)| k).collect()
+    };
+    crate::klog::log(
+        crate::klog::Level::Info,
+        alloc::format!(
+            "config: {} claves cargadas desde {} ({} cambios del journal)",
+            keys.len(),
+            source,
+            replayed
+        )
+        .as_str(),
+    );
+    let _ = persist(false);
+    notify(keys.as_slice());
+}
+
+/// Flag a reload when one of the files is written by someone else. Runs on
+/// the writing thread, so the reading waits for the next access.
+fn on_fs_event(event: &crate::fswatch::Event) {
+    if WRITING.load(Ordering::Acquire)
+        || event.volume != LOADED_VOLUME.load(Ordering::Acquire)
+        || event.dir != LOADED_DIR.load(Ordering::Acquire)
+    {
+        return;
+    }
+    if [CONFIG_FILE, CONFIG_OLD_FILE, CONFIG_JOURNAL_FILE]
+        .iter()
+        .any(|name| event.name.eq_ignore_ascii_case(name))
+    {
+        RELOAD_PENDING.store(true, Ordering::Release);
+    }
+}
+
+/// Load the files again and apply the keys that differ as changes of our
+/// own, then rewrite the snapshot so the disk matches what is in memory.
+/// Without a readable snapshot or journal the memory copy is kept.
+fn reload() {
+    let Ok(dir) = config_dir() else {
+        return;
+    };
+    let mut loaded = Registry::new();
+    let mut found = false;
+    for name in [CONFIG_FILE, CONFIG_OLD_FILE] {
+        if let Some(raw) = read_config_file(dir, name) {
+            if loaded.load_snapshot(raw.as_slice()).is_ok() {
+                found = true;
+                break;
+            }
+        }
+    }
+    if let Some(raw) = read_config_file(dir, CONFIG_JOURNAL_FILE) {
+        found |= !loaded.replay_journal(raw.as_slice()).is_empty();
+    }
+    if !found {
+        return;
+    }
+
+    let keys: Vec<String> = {
+        let mut reg = REGISTRY.lock();
+        let mut keys: Vec<String> = reg
+            .list("")
+            .into_iter()
+            .filter(|(key, _)| loaded.get(key).is_none())
+            .map(|(key, _)| key)
+            .collect();
+        for key in keys.iter() {
+            reg.unset(key.as_str());
+        }
+        for (key, value) in loaded.list("") {
+            if reg.set(key.as_str(), value) {
+                keys.push(key);
+            }
+        }
+        keys
+    };
+    if keys.is_empty() {
+        return;
+    }
+    crate::klog::log(
+        crate::klog::Level::Info,
+        alloc::format!("config: recargado desde disco ({} claves cambiadas)", keys.len()).as_str(),
+    );
+    let _ = persist(true);
+    notify(keys.as_slice());
+}
+
+/// Write the journal, folding it into a new snapshot when it has grown or
+/// `compact` is set. Before the volume is available only the in-memory
+/// journal is trimmed.
+fn persist(compact: bool) -> Result<(), &'static str> {
+    if !LOADED.load(Ordering::Acquire) {
+        let mut reg = REGISTRY.lock();
+        if reg.needs_compaction() {
+            reg.mark_compacted();
+        }
+        return Ok(());
+    }
+    WRITING.store(true, Ordering::Release);
+    let result = write_files(compact);
+    WRITING.store(false, Ordering::Release);
+    result
+}
+
+fn write_files(compact: bool) -> Result<(), &'static str> {
+    // The registry is the kernel's, whoever changed a key.
+    let _root = crate::perm::act_as(crate::perm::Cred::ROOT);
+    let dir = config_dir()?;
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let (journal, snapshot) = {
+        let reg = REGISTRY.lock();
+        let snapshot = if compact || reg.needs_compaction() {
+            Some(reg.encode_snapshot())
+        } else {
+            None
+        };
+        (reg.journal.clone(), snapshot)
+    };
+    fat.write_text_file_in_dir(dir, CONFIG_JOURNAL_FILE, journal.as_slice())?;
+    let Some(snapshot) = snapshot else {
+        return Ok(());
+    };
+
+    // The journal on disk is complete at this point, and it applies on top
+    // of both CONFIG.OLD and the new CONFIG.BIN, so a crash anywhere below
+    // still loads every change.
+    if let Some(previous) = read_config_file(dir, CONFIG_FILE) {
+        fat.write_text_file_in_dir(dir, CONFIG_OLD_FILE, previous.as_slice())?;
+    }
+    fat.write_text_file_in_dir(dir, CONFIG_FILE, snapshot.as_slice())?;
+    fat.write_text_file_in_dir(dir, CONFIG_JOURNAL_FILE, &[])?;
+    REGISTRY.lock().mark_compacted();
+    Ok(())
+}
+
+pub fn get(key: &str) -> Option<ConfigValue> {
+    ensure_loaded();
+    REGISTRY.lock().get(key).cloned()
+}
+
+pub fn get_bool(key: &str, default: bool) -> bool {
+    match get(key) {
+        Some(ConfigValue::Bool(v)) => v,
+        Some(ConfigValue::Int(v)) => v != 0,
+        _ => default,
+    }
+}
+
+pub fn get_int(key: &str, default: i64) -> i64 {
+    match get(key) {
+        Some(ConfigValue::Int(v)) => v,
+        _ => default,
+    }
+}
+
+/// Colors may also be stored as plain integers.
+pub fn get_color(key: &str, default: u32) -> u32 {
+    match get(key) {
+        Some(ConfigValue::Color(v)) => v & 0xFF_FFFF,
+        Some(ConfigValue::Int(v)) if (0..=0xFF_FFFF).contains(&v) => v as u32,
+        _ => default,
+    }
+}
+
+pub fn get_str(key: &str, default: &str) -> String {
+    match get(key) {
+        Some(ConfigValue::Str(v)) => v,
+        _ => String::from(default),
+    }
+}
+
+/// Store `value`, persist it and run the watchers. Setting the value a key
+/// already has is a no-op that still succeeds.
+pub fn set(key: &str, value: ConfigValue) -> Result<(), &'static str> {
+    if !valid_key(key) {
+        return Err("clave invalida (a-z 0-9 _ - separados por '.')");
+    }
+    if !valid_value(&value) {
+        return Err("valor demasiado largo");
+    }
+    ensure_loaded();
+    if !REGISTRY.lock().set(key, value) {
+        return Ok(());
+    }
+    let saved = persist(false);
+    notify(&[String::from(key)]);
+    saved
+}
+
+pub fn unset(key: &str) -> Result<bool, &'static str> {
+    ensure_loaded();
+    if !REGISTRY.lock().unset(key) {
+        return Ok(false);
+    }
+    let saved = persist(false);
+    notify(&[String::from(key)]);
+    saved.map(|_| true)
+}
+
+pub fn list(prefix: &str) -> Vec<(String, ConfigValue)> {
+    ensure_loaded();
+    REGISTRY.lock().list(prefix)
+}
+
+/// Sequence number of the newest change under `prefix`; pollers compare it
+/// with the value they saw last.
+pub fn revision(prefix: &str) -> u64 {
+    ensure_loaded();
+    REGISTRY.lock().revision(prefix)
+}
+
+pub fn key_count() -> usize {
+    REGISTRY.lock().len()
+}
+
+pub fn is_persistent() -> bool {
+    LOADED.load(Ordering::Acquire)
+}
+
+/// Fold the journal into a new snapshot now.
+pub fn compact() -> Result<(), &'static str> {
+    ensure_loaded();
+    if !is_persistent() {
+        return Err("sin volumen montado; cambios solo en memoria");
+    }
+    persist(true)
+}
+
+/// Shared implementation of `config`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim();
+    let (sub, rest) = args.split_once(' ').unwrap_or((args, ""));
+    let rest = rest.trim();
+    let mut out = Vec::new();
+    match sub {
+        "" | "list" => {
+            let items = list(rest);
+            if items.is_empty() {
+                out.push(String::from("config: sin claves."));
+            }
+            for (key, value) in items.iter() {
+                out.push(alloc::format!("{} = {} ({})", key, value.to_text(), value.type_name()));
+            }
+            out.push(alloc::format!(
+                "config: {} claves, revision {}, {}.",
+                key_count(),
+                revision(""),
+                if is_persistent() {
+                    "\\REDUXOS\\CONFIG.BIN"
+                } else {
+                    "solo memoria"
+                }
+            ));
+        }
+        "get" => match get(rest) {
+            Some(value) => out.push(alloc::format!("{} = {} ({})", rest, value.to_text(), value.type_name())),
+            None => out.push(alloc::format!("config: {} no existe.", rest)),
+        },
+        "set" => {
+            let Some((key, value)) = rest.split_once(' ') else {
+                out.push(String::from("Uso: config set <clave> <valor>"));
+                return out;
+            };
+            let value = ConfigValue::parse(value);
+            match set(key.trim(), value.clone()) {
+                Ok(()) => out.push(alloc::format!(
+                    "{} = {} ({})",
+                    key.trim(),
+                    value.to_text(),
+                    value.type_name()
+                )),
+                Err(e) => out.push(alloc::format!("config: {}", e)),
+            }
+        }
+        "unset" => match unset(rest) {
+            Ok(true) => out.push(alloc::format!("config: {} eliminada.", rest)),
+            Ok(false) => out.push(alloc::format!("config: {} no existe.", rest)),
+            Err(e) => out.push(alloc::format!("config: {}", e)),
+        },
+        "save" => match compact() {
+            Ok(()) => out.push(String::from("config: snapshot guardado en \\REDUXOS\\CONFIG.BIN.")),
+            Err(e) => out.push(alloc::format!("config: {}", e)),
+        },
+        _ => out.push(String::from(
+            "Uso: config [list [prefijo]|get <clave>|set <clave> <valor>|unset <clave>|save]",
+        )),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "config";
+
+    fn values_parse_typed() {
+        crate::selftest::ensure_eq(ConfigValue::parse("true"), ConfigValue::Bool(true), "bool")?;
+        crate::selftest::ensure_eq(ConfigValue::parse("-12"), ConfigValue::Int(-12), "int")?;
+        crate::selftest::ensure_eq(ConfigValue::parse("0x1f"), ConfigValue::Int(31), "hex")?;
+        crate::selftest::ensure_eq(ConfigValue::parse("#021f3f"), ConfigValue::Color(0x021F3F), "color")?;
+        crate::selftest::ensure_eq(ConfigValue::Color(0x021F3F).to_text().as_str(), "#021F3F", "color texto")?;
+        crate::selftest::ensure_eq(ConfigValue::parse("es-ES"), ConfigValue::Str(String::from("es-ES")), "str")?;
+        crate::selftest::ensure(valid_key("desktop.background") && !valid_key("Desktop.x") && !valid_key("a..b"), "claves")
+    }
+
+    fn snapshot_roundtrip_and_crc() {
+        let mut reg = Registry::new();
+        reg.set("desktop.background", ConfigValue::Color(0x112233));
+        reg.set("system.hostname", ConfigValue::parse("redux"));
+        reg.set("net.dhcp", ConfigValue::Bool(true));
+        let mut raw = reg.encode_snapshot();
+        let mut copy = Registry::new();
+        copy.load_snapshot(raw.as_slice()).map_err(String::from)?;
+        crate::selftest::ensure_eq(copy.list(""), reg.list(""), "contenido")?;
+        crate::selftest::ensure_eq(copy.seq(), 3, "seq")?;
+        let mid = raw.len() / 2;
+        raw[mid] ^= 0x40;
+        crate::selftest::ensure(copy.load_snapshot(raw.as_slice()).is_err(), "crc detecta corrupcion")
+    }
+
+    fn journal_replays_until_torn_record() {
+        let mut base = Registry::new();
+        base.set("a.x", ConfigValue::Int(1));
+        let snapshot = base.encode_snapshot();
+        base.set("a.y", ConfigValue::Int(2));
+        base.unset("a.x");
+        base.set("a.z", ConfigValue::Int(3));
+        let mut journal = base.journal.clone();
+        // Cut the last record short, as a crash mid-write would.
+        journal.truncate(journal.len() - 2);
+
+        let mut reg = Registry::new();
+        reg.load_snapshot(snapshot.as_slice()).map_err(String::from)?;
+        let keys = reg.replay_journal(journal.as_slice());
+        crate::selftest::ensure_eq(keys.len(), 2, "registros aplicados")?;
+        crate::selftest::ensure_eq(reg.get("a.x"), None, "unset aplicado")?;
+        crate::selftest::ensure_eq(reg.get("a.y"), Some(&ConfigValue::Int(2)), "set aplicado")?;
+        crate::selftest::ensure_eq(reg.get("a.z"), None, "registro roto ignorado")?;
+        crate::selftest::ensure_eq(reg.revision("a."), 3, "revision")
+    }
+
+    fn journal_skips_records_already_in_snapshot() {
+        let mut reg = Registry::new();
+        reg.set("k.a", ConfigValue::Int(1));
+        reg.set("k.a", ConfigValue::Int(2));
+        let journal = reg.journal.clone();
+        let snapshot = reg.encode_snapshot();
+        let mut copy = Registry::new();
+        copy.load_snapshot(snapshot.as_slice()).map_err(String::from)?;
+        crate::selftest::ensure(copy.replay_journal(journal.as_slice()).is_empty(), "nada nuevo")?;
+        crate::selftest::ensure_eq(copy.get("k.a"), Some(&ConfigValue::Int(2)), "valor final")
+    }
+}
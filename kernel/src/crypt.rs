@@ -0,0 +1,681 @@
+//! Encrypted data partition: an AES-XTS layer under the filesystem.
+//!
+//! Like dm-crypt with a detached LUKS header, an encrypted partition keeps
+//! its header in its last `HEADER_SECTORS` sectors and the filesystem in
+//! the sectors before it. Every payload sector is encrypted with
+//! AES-256-XTS, the tweak being its number counted from the start of the
+//! partition ("plain64"), so exFAT sits at sector 0 as it would unencrypted.
+//!
+//! The 64-byte master key never changes. The header keeps it wrapped with
+//! AES-256-GCM under a key derived from the user's password with
+//! PBKDF2-HMAC-SHA256, and, when `FLAG_TPM` is set, a second copy sealed in
+//! the TPM to the boot PCRs (see `tpm`).
+//!
+//! `fat32` and the installer call `decrypt` after reading and `encrypt`
+//! before writing through a UEFI block handle. Sectors outside an unlocked
+//! mapping pass through untouched, so one disk handle can carry the
+//! encrypted partition next to plain ones.
+//!
+//! `unlock_at_boot` runs before anything mounts the data partition: it tries
+//! the TPM copy, then asks for the password on the login screen. A partition
+//! that stays locked is not mounted; `crypt unlock` can open it later.
+//! VirtIO and NVMe devices driven by the kernel itself are not covered.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use uefi::boot::{self, OpenProtocolAttributes, OpenProtocolParams};
+use uefi::proto::media::block::BlockIO;
+use uefi::Handle;
+
+use crate::crypto::{AesGcm, AesXts};
+use crate::fat32::Fat32;
+use crate::framebuffer::{self, rgb};
+use crate::i18n::{tr, trf};
+use crate::input::{self, RuntimeInput, RuntimeKey};
+use crate::println;
+use crate::spinlock::SpinLock;
+
+const SECTOR_SIZE: usize = 512;
+/// Sectors at the end of the partition that hold the header.
+pub const HEADER_SECTORS: u64 = 8;
+const HEADER_BYTES: usize = HEADER_SECTORS as usize * SECTOR_SIZE;
+const MAGIC: &[u8; 8] = b"REDUXCRY";
+const VERSION: u16 = 1;
+const CIPHER_AES_XTS_PLAIN64: u16 = 1;
+/// The header holds a TPM-sealed copy of the master key.
+pub const FLAG_TPM: u32 = 1;
+pub const MASTER_KEY_LEN: usize = crate::tpm::DATA_KEY_LEN;
+pub const KDF_ITERATIONS: u32 = 200_000;
+pub const MIN_PASSWORD_LEN: usize = 8;
+const MAX_PASSWORD_LEN: usize = 64;
+/// Smallest partition worth encrypting: the header plus 1 MiB.
+const MIN_PARTITION_SECTORS: u64 = HEADER_SECTORS + 2048;
+
+const OFF_VERSION: usize = 8;
+const OFF_CIPHER: usize = 10;
+const OFF_FLAGS: usize = 12;
+const OFF_PAYLOAD: usize = 16;
+const OFF_ITERATIONS: usize = 24;
+const OFF_SALT: usize = 32;
+const OFF_NONCE: usize = 64;
+const OFF_WRAPPED: usize = 76;
+const OFF_TAG: usize = OFF_WRAPPED + MASTER_KEY_LEN;
+const OFF_TPM_LEN: usize = OFF_TAG + 16;
+const OFF_TPM: usize = OFF_TPM_LEN + 2;
+const OFF_CRC: usize = HEADER_BYTES - 4;
+/// Header bytes the key wrap authenticates: everything before the nonce.
+const AAD_LEN: usize = OFF_NONCE;
+
+/// What the last `HEADER_SECTORS` sectors of an encrypted partition say.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub flags: u32,
+    /// Sectors of encrypted data before the header.
+    pub payload_sectors: u64,
+    pub iterations: u32,
+    salt: [u8; 32],
+    nonce: [u8; 12],
+    wrapped: [u8; MASTER_KEY_LEN],
+    tag: [u8; 16],
+    /// `tpm::Sealed::encode` of the master key; empty until sealed.
+    tpm: Vec<u8>,
+}
+
+impl Header {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut raw = alloc::vec![0u8; HEADER_BYTES];
+        raw[..8].copy_from_slice(MAGIC);
+        raw[OFF_VERSION..OFF_VERSION + 2].copy_from_slice(&VERSION.to_le_bytes());
+        raw[OFF_CIPHER..OFF_CIPHER + 2].copy_from_slice(&CIPHER_AES_XTS_PLAIN64.to_le_bytes());
+        raw[OFF_FLAGS..OFF_FLAGS + 4].copy_from_slice(&self.flags.to_le_bytes());
+        raw[OFF_PAYLOAD..OFF_PAYLOAD + 8].copy_from_slice(&self.payload_sectors.to_le_bytes());
+        raw[OFF_ITERATIONS..OFF_ITERATIONS + 4].copy_from_slice(&self.iterations.to_le_bytes());
+        raw[OFF_SALT..OFF_SALT + 32].copy_from_slice(&self.salt);
+        raw[OFF_NONCE..OFF_NONCE + 12].copy_from_slice(&self.nonce);
+        raw[OFF_WRAPPED..OFF_WRAPPED + MASTER_KEY_LEN].copy_from_slice(&self.wrapped);
+        raw[OFF_TAG..OFF_TAG + 16].copy_from_slice(&self.tag);
+        let slot = self.tpm.len().min(OFF_CRC - OFF_TPM);
+        raw[OFF_TPM_LEN..OFF_TPM_LEN + 2].copy_from_slice(&(slot as u16).to_le_bytes());
+        raw[OFF_TPM..OFF_TPM + slot].copy_from_slice(&self.tpm[..slot]);
+        let crc = crate::compress::crc32(&raw[..OFF_CRC]);
+        raw[OFF_CRC..].copy_from_slice(&crc.to_le_bytes());
+        raw
+    }
+
+    pub fn decode(raw: &[u8]) -> Result<Self, &'static str> {
+        if raw.len() < HEADER_BYTES || &raw[..8] != MAGIC {
+            return Err("crypt: sin cabecera");
+        }
+        let u16_at = |at: usize| u16::from_le_bytes([raw[at], raw[at + 1]]);
+        let u32_at = |at: usize| u32::from_le_bytes([raw[at], raw[at + 1], raw[at + 2], raw[at + 3]]);
+        if u32_at(OFF_CRC) != crate::compress::crc32(&raw[..OFF_CRC]) {
+            return Err("crypt: cabecera corrupta");
+        }
+        if u16_at(OFF_VERSION) != VERSION || u16_at(OFF_CIPHER) != CIPHER_AES_XTS_PLAIN64 {
+            return Err("crypt: version de cabecera no soportada");
+        }
+        let slot = u16_at(OFF_TPM_LEN) as usize;
+        if slot > OFF_CRC - OFF_TPM {
+            return Err("crypt: cabecera corrupta");
+        }
+        let mut payload = [0u8; 8];
+        payload.copy_from_slice(&raw[OFF_PAYLOAD..OFF_PAYLOAD + 8]);
+        let mut header = Self {
+            flags: u32_at(OFF_FLAGS),
+            payload_sectors: u64::from_le_bytes(payload),
+            iterations: u32_at(OFF_ITERATIONS),
+            salt: [0; 32],
+            nonce: [0; 12],
+            wrapped: [0; MASTER_KEY_LEN],
+            tag: [0; 16],
+            tpm: raw[OFF_TPM..OFF_TPM + slot].to_vec(),
+        };
+        header.salt.copy_from_slice(&raw[OFF_SALT..OFF_SALT + 32]);
+        header.nonce.copy_from_slice(&raw[OFF_NONCE..OFF_NONCE + 12]);
+        header
+            .wrapped
+            .copy_from_slice(&raw[OFF_WRAPPED..OFF_WRAPPED + MASTER_KEY_LEN]);
+        header.tag.copy_from_slice(&raw[OFF_TAG..OFF_TAG + 16]);
+        Ok(header)
+    }
+
+    /// The first `AAD_LEN` bytes of the encoded header.
+    fn aad(&self) -> [u8; AAD_LEN] {
+        let mut aad = [0u8; AAD_LEN];
+        aad.copy_from_slice(&self.encode()[..AAD_LEN]);
+        aad
+    }
+
+    /// A header for `master`, wrapped under `password`.
+    fn wrap(master: &[u8; MASTER_KEY_LEN], password: &[u8], payload_sectors: u64, flags: u32, iterations: u32) -> Self {
+        let mut header = Self {
+            flags,
+            payload_sectors,
+            iterations,
+            salt: [0; 32],
+            nonce: [0; 12],
+            wrapped: *master,
+            tag: [0; 16],
+            tpm: Vec::new(),
+        };
+        crate::random::fill(&mut header.salt);
+        crate::random::fill(&mut header.nonce);
+        let mut kek = [0u8; 32];
+        crate::crypto::pbkdf2_sha256(password, &header.salt, iterations, &mut kek);
+        let aad = header.aad();
+        let cipher = AesGcm::new(&kek).expect("crypt: AES-256 key");
+        header.tag = cipher
+            .seal_in_place(&header.nonce, &aad, &mut header.wrapped)
+            .expect("crypt: key wrap");
+        kek.fill(0);
+        header
+    }
+
+    /// The master key, if `password` is the one it was wrapped with.
+    fn unwrap(&self, password: &[u8]) -> Option<[u8; MASTER_KEY_LEN]> {
+        let mut kek = [0u8; 32];
+        crate::crypto::pbkdf2_sha256(password, &self.salt, self.iterations, &mut kek);
+        let cipher = AesGcm::new(&kek)?;
+        kek.fill(0);
+        let mut master = self.wrapped;
+        if cipher.open_in_place(&self.nonce, &self.aad(), &mut master, &self.tag) {
+            Some(master)
+        } else {
+            master.fill(0);
+            None
+        }
+    }
+
+    fn unseal(&self) -> Result<[u8; MASTER_KEY_LEN], String> {
+        if self.tpm.is_empty() {
+            return Err(String::from("crypt: sin copia en el TPM"));
+        }
+        let sealed = crate::tpm::Sealed::decode(&self.tpm).map_err(String::from)?;
+        let mut secret = crate::tpm::unseal(&sealed)?;
+        let mut master = [0u8; MASTER_KEY_LEN];
+        let ok = secret.len() == MASTER_KEY_LEN;
+        if ok {
+            master.copy_from_slice(&secret);
+        }
+        secret.fill(0);
+        if ok {
+            Ok(master)
+        } else {
+            Err(String::from("crypt: clave del TPM no valida"))
+        }
+    }
+}
+
+/// An encrypted partition found on a UEFI block handle.
+struct Volume {
+    /// `Handle::as_ptr` of the block handle the mapping applies to.
+    handle: usize,
+    /// First payload sector, in 512-byte units of that handle.
+    start: u64,
+    header: Header,
+    cipher: Option<AesXts>,
+}
+
+impl Volume {
+    fn contains(&self, lba: u64, sectors: u64) -> bool {
+        lba < self.start + self.header.payload_sectors && lba + sectors > self.start
+    }
+}
+
+static VOLUMES: SpinLock<Vec<Volume>> = SpinLock::new(Vec::new());
+/// Unlocked volumes; lets the I/O hooks skip the lock when there are none.
+static MAPPED: AtomicUsize = AtomicUsize::new(0);
+
+fn key(handle: Handle) -> usize {
+    handle.as_ptr() as usize
+}
+
+fn handle_of(key: usize) -> Option<Handle> {
+    unsafe { Handle::from_ptr(key as *mut core::ffi::c_void) }
+}
+
+/// The cipher and payload range of the unlocked volume on `handle` that
+/// overlaps `sectors` sectors at `lba`.
+fn mapping(handle: Handle, lba: u64, sectors: u64) -> Option<(AesXts, u64, u64)> {
+    if MAPPED.load(Ordering::Acquire) == 0 {
+        return None;
+    }
+    let handle = key(handle);
+    let volumes = VOLUMES.lock();
+    volumes
+        .iter()
+        .find(|volume| volume.handle == handle && volume.cipher.is_some() && volume.contains(lba, sectors))
+        .map(|volume| {
+            let end = volume.start + volume.header.payload_sectors;
+            (volume.cipher.clone().unwrap(), volume.start, end)
+        })
+}
+
+/// Decrypt in place the sectors of `data`, read from `lba` on `handle`,
+/// that fall inside an unlocked volume.
+pub fn decrypt(handle: Handle, lba: u64, data: &mut [u8]) {
+    let sectors = (data.len() / SECTOR_SIZE) as u64;
+    let Some((cipher, start, end)) = mapping(handle, lba, sectors) else {
+        return;
+    };
+    for (index, sector) in data.chunks_exact_mut(SECTOR_SIZE).enumerate() {
+        let lba = lba + index as u64;
+        if lba >= start && lba < end {
+            cipher.decrypt_sector(lba - start, sector);
+        }
+    }
+}
+
+/// What to write instead of `data` at `lba` on `handle`, or `None` when no
+/// unlocked volume covers it.
+pub fn encrypt(handle: Handle, lba: u64, data: &[u8]) -> Option<Vec<u8>> {
+    let sectors = (data.len() / SECTOR_SIZE) as u64;
+    let (cipher, start, end) = mapping(handle, lba, sectors)?;
+    let mut out = data.to_vec();
+    for (index, sector) in out.chunks_exact_mut(SECTOR_SIZE).enumerate() {
+        let lba = lba + index as u64;
+        if lba >= start && lba < end {
+            cipher.encrypt_sector(lba - start, sector);
+        }
+    }
+    Some(out)
+}
+
+/// Whether an encrypted volume, locked or not, lives on `handle`.
+pub fn is_mapped(handle: Handle) -> bool {
+    let handle = key(handle);
+    VOLUMES.lock().iter().any(|volume| volume.handle == handle)
+}
+
+fn add(handle: Handle, start: u64, header: Header, cipher: Option<AesXts>) {
+    let unlocked = cipher.is_some();
+    let mut volumes = VOLUMES.lock();
+    let handle = key(handle);
+    if let Some(volume) = volumes.iter_mut().find(|v| v.handle == handle && v.start == start) {
+        if volume.cipher.is_some() {
+            MAPPED.fetch_sub(1, Ordering::AcqRel);
+        }
+        volume.header = header;
+        volume.cipher = cipher;
+    } else {
+        volumes.push(Volume {
+            handle,
+            start,
+            header,
+            cipher,
+        });
+    }
+    if unlocked {
+        MAPPED.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+/// Drop every volume registered on `handle`.
+pub fn forget(handle: Handle) {
+    let handle = key(handle);
+    let mut volumes = VOLUMES.lock();
+    let unlocked = volumes
+        .iter()
+        .filter(|volume| volume.handle == handle && volume.cipher.is_some())
+        .count();
+    volumes.retain(|volume| volume.handle != handle);
+    MAPPED.fetch_sub(unlocked, Ordering::AcqRel);
+}
+
+fn read_header(handle: Handle, start: u64, sectors: u64) -> Result<Header, &'static str> {
+    if sectors < MIN_PARTITION_SECTORS {
+        return Err("crypt: particion demasiado pequena");
+    }
+    let first = start + sectors - HEADER_SECTORS;
+    let mut raw = alloc::vec![0u8; HEADER_BYTES];
+    for (index, sector) in raw.chunks_exact_mut(SECTOR_SIZE).enumerate() {
+        if !Fat32::read_sector_from_uefi_handle(handle, first + index as u64, sector) {
+            return Err("crypt: error de lectura");
+        }
+    }
+    let header = Header::decode(&raw)?;
+    if header.payload_sectors != sectors - HEADER_SECTORS {
+        return Err("crypt: tamano de particion distinto al de la cabecera");
+    }
+    Ok(header)
+}
+
+fn write_header(handle: Handle, start: u64, header: &Header) -> Result<(), &'static str> {
+    let first = start + header.payload_sectors;
+    for (index, sector) in header.encode().chunks_exact(SECTOR_SIZE).enumerate() {
+        if !Fat32::write_sector_from_uefi_handle(handle, first + index as u64, sector) {
+            return Err("crypt: error de escritura");
+        }
+    }
+    Ok(())
+}
+
+/// Whether `[start, start + sectors)` on `handle` already holds a header.
+pub fn has_header(handle: Handle, start: u64, sectors: u64) -> bool {
+    read_header(handle, start, sectors).is_ok()
+}
+
+/// Logical partitions as (handle, 512-byte sectors).
+fn partitions() -> Vec<(Handle, u64)> {
+    let mut out = Vec::new();
+    for handle in boot::find_handles::<BlockIO>().unwrap_or_default() {
+        let params = OpenProtocolParams {
+            handle,
+            agent: boot::image_handle(),
+            controller: None,
+        };
+        let Ok(blk) = (unsafe { boot::open_protocol::<BlockIO>(params, OpenProtocolAttributes::GetProtocol) }) else {
+            continue;
+        };
+        let media = blk.media();
+        if !media.is_media_present() || !media.is_logical_partition() {
+            continue;
+        }
+        let bytes = media
+            .last_block()
+            .saturating_add(1)
+            .saturating_mul(media.block_size() as u64);
+        out.push((handle, bytes / SECTOR_SIZE as u64));
+    }
+    out
+}
+
+/// Register the encrypted partitions on the UEFI partition handles. Returns
+/// how many are known.
+pub fn scan() -> usize {
+    for (handle, sectors) in partitions() {
+        if is_mapped(handle) {
+            continue;
+        }
+        if let Ok(header) = read_header(handle, 0, sectors) {
+            add(handle, 0, header, None);
+        }
+    }
+    VOLUMES.lock().len()
+}
+
+/// Make `[start, start + sectors)` on `handle` an encrypted volume: a fresh
+/// master key, a header at the end, and an unlocked mapping over the rest,
+/// ready for a filesystem. The old contents are lost. With `use_tpm` the
+/// key is sealed in the TPM the first time the password unlocks it at boot.
+/// Returns the sectors left for the filesystem.
+pub fn create(handle: Handle, start: u64, sectors: u64, password: &str, use_tpm: bool) -> Result<u64, &'static str> {
+    if password.len() < MIN_PASSWORD_LEN {
+        return Err("crypt: contrasena demasiado corta");
+    }
+    if sectors < MIN_PARTITION_SECTORS {
+        return Err("crypt: particion demasiado pequena");
+    }
+    let mut master = [0u8; MASTER_KEY_LEN];
+    crate::random::fill(&mut master);
+    let payload = sectors - HEADER_SECTORS;
+    let flags = if use_tpm { FLAG_TPM } else { 0 };
+    let header = Header::wrap(&master, password.as_bytes(), payload, flags, KDF_ITERATIONS);
+    let cipher = AesXts::new(&master);
+    master.fill(0);
+    write_header(handle, start, &header)?;
+    add(handle, start, header, cipher);
+    Ok(payload)
+}
+
+/// Open a volume with `master` and, when asked to, put a fresh TPM copy of
+/// it in its header.
+fn open(handle: usize, start: u64, mut header: Header, master: &mut [u8; MASTER_KEY_LEN], reseal: bool) {
+    let Some(handle) = handle_of(handle) else {
+        return;
+    };
+    if reseal && header.flags & FLAG_TPM != 0 && crate::tpm::present() {
+        match crate::tpm::seal(master.as_slice()) {
+            Ok(sealed) => {
+                header.tpm = sealed.encode();
+                if let Err(err) = write_header(handle, start, &header) {
+                    crate::klog::log(crate::klog::Level::Warning, err);
+                }
+            }
+            Err(err) => crate::klog::log(crate::klog::Level::Warning, err.as_str()),
+        }
+    }
+    let cipher = AesXts::new(master.as_slice());
+    master.fill(0);
+    add(handle, start, header, cipher);
+}
+
+/// Locked volumes, copied out so key derivation runs without the lock.
+fn locked() -> Vec<(usize, u64, Header)> {
+    VOLUMES
+        .lock()
+        .iter()
+        .filter(|volume| volume.cipher.is_none())
+        .map(|volume| (volume.handle, volume.start, volume.header.clone()))
+        .collect()
+}
+
+pub fn locked_count() -> usize {
+    VOLUMES.lock().iter().filter(|volume| volume.cipher.is_none()).count()
+}
+
+/// Unlock every locked volume whose TPM copy still unseals. Returns how many
+/// opened.
+pub fn unlock_with_tpm() -> usize {
+    if !crate::tpm::present() {
+        return 0;
+    }
+    let mut opened = 0;
+    for (handle, start, header) in locked() {
+        if header.flags & FLAG_TPM == 0 {
+            continue;
+        }
+        match header.unseal() {
+            Ok(mut master) => {
+                open(handle, start, header, &mut master, false);
+                opened += 1;
+            }
+            Err(err) => crate::klog::log(crate::klog::Level::Warning, err.as_str()),
+        }
+    }
+    opened
+}
+
+/// Unlock every locked volume `password` opens. Volumes that want a TPM copy
+/// get a new one sealed to this boot's PCRs. Returns how many opened.
+pub fn unlock_with_password(password: &[u8]) -> usize {
+    let mut opened = 0;
+    for (handle, start, header) in locked() {
+        if let Some(mut master) = header.unwrap(password) {
+            open(handle, start, header, &mut master, true);
+            opened += 1;
+        }
+    }
+    opened
+}
+
+fn draw_prompt(locked: usize, typed: usize, status: &str, status_color: u32) {
+    let (w, _) = framebuffer::dimensions();
+    framebuffer::clear(rgb(10, 14, 24));
+    framebuffer::rect(0, 0, w, 72, rgb(12, 34, 70));
+    framebuffer::draw_text_5x7(24, 22, tr("crypt.title").as_str(), rgb(230, 240, 255));
+    framebuffer::draw_text_5x7(24, 96, trf("crypt.prompt", &[&locked]).as_str(), rgb(230, 230, 230));
+    let mut mask = String::from("> ");
+    for _ in 0..typed {
+        mask.push('*');
+    }
+    framebuffer::draw_text_5x7(24, 120, mask.as_str(), rgb(230, 240, 255));
+    framebuffer::draw_text_5x7(24, 144, status, status_color);
+    framebuffer::draw_text_5x7(24, 176, tr("crypt.hint").as_str(), rgb(170, 190, 218));
+    framebuffer::present();
+}
+
+/// Find the encrypted partitions and open them before anything mounts them:
+/// with the TPM when it can, else with the password typed on the login
+/// screen when `prompt` allows one. Esc boots without them.
+pub fn unlock_at_boot(prompt: bool) {
+    if scan() == 0 {
+        return;
+    }
+    let by_tpm = unlock_with_tpm();
+    if by_tpm > 0 {
+        println(alloc::format!("Crypt: {} particion(es) abiertas con el TPM", by_tpm).as_str());
+    }
+    if locked_count() == 0 || !prompt {
+        return;
+    }
+    let mut password: Vec<u8> = Vec::new();
+    let mut status = String::new();
+    let mut status_color = rgb(255, 212, 138);
+    let mut dirty = true;
+    loop {
+        let locked = locked_count();
+        if locked == 0 {
+            break;
+        }
+        if dirty {
+            draw_prompt(locked, password.len(), status.as_str(), status_color);
+            dirty = false;
+        }
+        match input::poll_input_uefi() {
+            Some(RuntimeInput::Char(ch)) if ch.is_ascii() && !ch.is_ascii_control() => {
+                if password.len() < MAX_PASSWORD_LEN {
+                    password.push(ch as u8);
+                    dirty = true;
+                }
+            }
+            Some(RuntimeInput::Backspace) => {
+                if let Some(last) = password.last_mut() {
+                    *last = 0;
+                    password.pop();
+                    dirty = true;
+                }
+            }
+            Some(RuntimeInput::Enter) if !password.is_empty() => {
+                draw_prompt(
+                    locked,
+                    password.len(),
+                    tr("crypt.deriving").as_str(),
+                    rgb(255, 212, 138),
+                );
+                let opened = unlock_with_password(&password);
+                password.fill(0);
+                password.clear();
+                if opened == 0 {
+                    status = tr("crypt.wrong");
+                    status_color = rgb(255, 159, 159);
+                } else {
+                    println(alloc::format!("Crypt: {} particion(es) abiertas con contrasena", opened).as_str());
+                    status = String::new();
+                }
+                dirty = true;
+            }
+            Some(RuntimeInput::Key(RuntimeKey::Esc)) => {
+                password.fill(0);
+                println("Crypt: particion de datos sin abrir");
+                break;
+            }
+            _ => {}
+        }
+        boot::stall(4_000);
+    }
+}
+
+pub fn status_lines() -> Vec<String> {
+    let volumes = VOLUMES.lock();
+    if volumes.is_empty() {
+        return alloc::vec![String::from("Crypt: sin particiones cifradas")];
+    }
+    volumes
+        .iter()
+        .enumerate()
+        .map(|(index, volume)| {
+            alloc::format!(
+                "Crypt {}: {} MiB AES-XTS, {}, PBKDF2 {} iteraciones, TPM {}",
+                index,
+                volume.header.payload_sectors / 2048,
+                if volume.cipher.is_some() { "abierta" } else { "cerrada" },
+                volume.header.iterations,
+                match (volume.header.flags & FLAG_TPM != 0, volume.header.tpm.is_empty()) {
+                    (false, _) => "no",
+                    (true, true) => "pendiente",
+                    (true, false) => "si",
+                }
+            )
+        })
+        .collect()
+}
+
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim();
+    let (verb, rest) = args.split_once(' ').unwrap_or((args, ""));
+    match verb {
+        "" | "status" => status_lines(),
+        "unlock" => {
+            scan();
+            let password = rest.trim();
+            let opened = if password.is_empty() {
+                unlock_with_tpm()
+            } else {
+                unlock_with_password(password.as_bytes())
+            };
+            if opened == 0 {
+                alloc::vec![String::from("Crypt: ninguna particion abierta")]
+            } else {
+                alloc::vec![alloc::format!(
+                    "Crypt: {} particion(es) abiertas; monta la de datos con el gestor de archivos",
+                    opened
+                )]
+            }
+        }
+        _ => alloc::vec![String::from("Uso: crypt [status|unlock [contrasena]]")],
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "crypt";
+
+    fn header_round_trip() {
+        let mut header = Header::wrap(&[7u8; MASTER_KEY_LEN], b"contrasena", 4096, FLAG_TPM, 16);
+        header.tpm = alloc::vec![1, 2, 3, 4];
+        let raw = header.encode();
+        crate::selftest::ensure_eq(raw.len(), HEADER_BYTES, "tamano de cabecera")?;
+        crate::selftest::ensure_eq(Header::decode(&raw), Ok(header.clone()), "cabecera decodificada")?;
+        let mut corrupt = raw.clone();
+        corrupt[OFF_PAYLOAD] ^= 1;
+        crate::selftest::ensure(Header::decode(&corrupt).is_err(), "CRC no detecta el cambio")
+    }
+
+    fn password_wraps_master_key() {
+        let master = [0x5Au8; MASTER_KEY_LEN];
+        let header = Header::wrap(&master, b"contrasena", 4096, 0, 16);
+        crate::selftest::ensure(header.wrapped != master, "clave maestra sin cifrar")?;
+        crate::selftest::ensure_eq(header.unwrap(b"contrasena"), Some(master), "contrasena correcta")?;
+        crate::selftest::ensure_eq(header.unwrap(b"contrasenb"), None, "contrasena incorrecta")?;
+        let mut moved = header.clone();
+        moved.payload_sectors += 1;
+        crate::selftest::ensure_eq(moved.unwrap(b"contrasena"), None, "cabecera alterada")
+    }
+
+    fn hooks_cover_only_the_payload() {
+        let handle = boot::image_handle();
+        let header = Header::wrap(&[3u8; MASTER_KEY_LEN], b"contrasena", 4, 0, 16);
+        add(handle, 10, header, AesXts::new(&[3u8; MASTER_KEY_LEN]));
+        let plain = [0xA5u8; 3 * SECTOR_SIZE];
+        let sealed = encrypt(handle, 9, &plain);
+        let sealed = match sealed {
+            Some(sealed) => sealed,
+            None => {
+                forget(handle);
+                return Err("sin mapeo");
+            }
+        };
+        let mut back = sealed.clone();
+        decrypt(handle, 9, &mut back);
+        let outside = encrypt(handle, 20, &plain);
+        forget(handle);
+        crate::selftest::ensure_eq(&sealed[..SECTOR_SIZE], &plain[..SECTOR_SIZE], "sector previo cifrado")?;
+        crate::selftest::ensure(sealed[SECTOR_SIZE..] != plain[SECTOR_SIZE..], "carga sin cifrar")?;
+        crate::selftest::ensure(sealed[SECTOR_SIZE..2 * SECTOR_SIZE] != sealed[2 * SECTOR_SIZE..], "tweak repetido")?;
+        crate::selftest::ensure_eq(back.as_slice(), plain.as_slice(), "descifrado")?;
+        crate::selftest::ensure(outside.is_none(), "sector fuera del volumen")?;
+        crate::selftest::ensure(!is_mapped(handle), "mapeo tras forget")
+    }
+}
@@ -0,0 +1,303 @@
+//! AES-GCM on AES-NI and PCLMULQDQ.
+//!
+//! Counter mode runs four blocks at a time; GHASH multiplies in the
+//! byte-reversed domain with carry-less multiplication and reduces modulo the
+//! GCM polynomial (Intel's "Carry-Less Multiplication and Its Usage for
+//! Computing the GCM Mode", algorithm 5). Every function needs AES-NI,
+//! PCLMULQDQ and SSSE3, and runs inside `super::with_simd`.
+
+use core::arch::x86_64::*;
+
+const BSWAP_MASK: [u8; 16] = [15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0];
+
+/// Expanded key and GHASH key, kept as bytes so the struct never carries a
+/// vector type outside the SIMD functions.
+pub struct Gcm {
+    round_keys: [[u8; 16]; 15],
+    rounds: usize,
+    /// `H = AES(K, 0)`, byte-reversed.
+    h: [u8; 16],
+}
+
+impl Drop for Gcm {
+    fn drop(&mut self) {
+        for key in self.round_keys.iter_mut().chain(core::iter::once(&mut self.h)) {
+            unsafe { core::ptr::write_volatile(key, [0; 16]) };
+        }
+    }
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn load(bytes: &[u8; 16]) -> __m128i {
+    _mm_loadu_si128(bytes.as_ptr() as *const __m128i)
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn store(value: __m128i) -> [u8; 16] {
+    let mut out = [0u8; 16];
+    _mm_storeu_si128(out.as_mut_ptr() as *mut __m128i, value);
+    out
+}
+
+/// Previous round key folded with the `aeskeygenassist` word.
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn mix(key: __m128i, assist: __m128i) -> __m128i {
+    let mut key = key;
+    key = _mm_xor_si128(key, _mm_slli_si128(key, 4));
+    key = _mm_xor_si128(key, _mm_slli_si128(key, 4));
+    key = _mm_xor_si128(key, _mm_slli_si128(key, 4));
+    _mm_xor_si128(key, assist)
+}
+
+macro_rules! assist {
+    ($key:expr, $rcon:literal, $word:literal) => {
+        _mm_shuffle_epi32(_mm_aeskeygenassist_si128($key, $rcon), $word)
+    };
+}
+
+#[target_feature(enable = "aes,sse2")]
+unsafe fn expand128(key: &[u8; 16], keys: &mut [__m128i; 15]) {
+    let mut k = load(key);
+    keys[0] = k;
+    macro_rules! round {
+        ($i:literal, $rcon:literal) => {
+            k = mix(k, assist!(k, $rcon, 0xff));
+            keys[$i] = k;
+        };
+    }
+    round!(1, 0x01);
+    round!(2, 0x02);
+    round!(3, 0x04);
+    round!(4, 0x08);
+    round!(5, 0x10);
+    round!(6, 0x20);
+    round!(7, 0x40);
+    round!(8, 0x80);
+    round!(9, 0x1b);
+    round!(10, 0x36);
+}
+
+#[target_feature(enable = "aes,sse2")]
+unsafe fn expand256(key: &[u8; 32], keys: &mut [__m128i; 15]) {
+    let mut a = _mm_loadu_si128(key.as_ptr() as *const __m128i);
+    let mut b = _mm_loadu_si128(key.as_ptr().add(16) as *const __m128i);
+    keys[0] = a;
+    keys[1] = b;
+    macro_rules! round {
+        ($i:literal, $rcon:literal) => {
+            a = mix(a, assist!(b, $rcon, 0xff));
+            keys[$i] = a;
+            b = mix(b, assist!(a, 0x00, 0xaa));
+            keys[$i + 1] = b;
+        };
+    }
+    round!(2, 0x01);
+    round!(4, 0x02);
+    round!(6, 0x04);
+    round!(8, 0x08);
+    round!(10, 0x10);
+    round!(12, 0x20);
+    a = mix(a, assist!(b, 0x40, 0xff));
+    keys[14] = a;
+}
+
+#[inline]
+#[target_feature(enable = "aes,sse2")]
+unsafe fn encrypt(keys: &[__m128i; 15], rounds: usize, block: __m128i) -> __m128i {
+    let mut x = _mm_xor_si128(block, keys[0]);
+    for key in keys[1..rounds].iter() {
+        x = _mm_aesenc_si128(x, *key);
+    }
+    _mm_aesenclast_si128(x, keys[rounds])
+}
+
+#[inline]
+#[target_feature(enable = "aes,sse2")]
+unsafe fn encrypt4(keys: &[__m128i; 15], rounds: usize, blocks: &mut [__m128i; 4]) {
+    for block in blocks.iter_mut() {
+        *block = _mm_xor_si128(*block, keys[0]);
+    }
+    for key in keys[1..rounds].iter() {
+        for block in blocks.iter_mut() {
+            *block = _mm_aesenc_si128(*block, *key);
+        }
+    }
+    for block in blocks.iter_mut() {
+        *block = _mm_aesenclast_si128(*block, keys[rounds]);
+    }
+}
+
+/// Counter block `n` for the 96-bit nonce held in `base` (last word zero).
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn counter(base: __m128i, n: u32) -> __m128i {
+    _mm_or_si128(base, _mm_set_epi32(n.swap_bytes() as i32, 0, 0, 0))
+}
+
+/// `a * b` in GF(2^128), both byte-reversed.
+#[inline]
+#[target_feature(enable = "pclmulqdq,sse2")]
+unsafe fn gfmul(a: __m128i, b: __m128i) -> __m128i {
+    let mut lo = _mm_clmulepi64_si128(a, b, 0x00);
+    let mut mid = _mm_xor_si128(_mm_clmulepi64_si128(a, b, 0x10), _mm_clmulepi64_si128(a, b, 0x01));
+    let mut hi = _mm_clmulepi64_si128(a, b, 0x11);
+    lo = _mm_xor_si128(lo, _mm_slli_si128(mid, 8));
+    hi = _mm_xor_si128(hi, _mm_srli_si128(mid, 8));
+
+    // The operands are bit-reflected: shift the 256-bit product left by one.
+    let lo_carry = _mm_srli_epi32(lo, 31);
+    let hi_carry = _mm_srli_epi32(hi, 31);
+    lo = _mm_slli_epi32(lo, 1);
+    hi = _mm_slli_epi32(hi, 1);
+    lo = _mm_or_si128(lo, _mm_slli_si128(lo_carry, 4));
+    hi = _mm_or_si128(hi, _mm_slli_si128(hi_carry, 4));
+    hi = _mm_or_si128(hi, _mm_srli_si128(lo_carry, 12));
+
+    // Reduce modulo x^128 + x^7 + x^2 + x + 1.
+    mid = _mm_xor_si128(_mm_slli_epi32(lo, 31), _mm_slli_epi32(lo, 30));
+    mid = _mm_xor_si128(mid, _mm_slli_epi32(lo, 25));
+    let spill = _mm_srli_si128(mid, 4);
+    lo = _mm_xor_si128(lo, _mm_slli_si128(mid, 12));
+    let mut fold = _mm_xor_si128(_mm_srli_epi32(lo, 1), _mm_srli_epi32(lo, 2));
+    fold = _mm_xor_si128(fold, _mm_srli_epi32(lo, 7));
+    fold = _mm_xor_si128(fold, spill);
+    lo = _mm_xor_si128(lo, fold);
+    _mm_xor_si128(hi, lo)
+}
+
+/// Absorb `data` into the GHASH accumulator, zero-padding the last block.
+#[target_feature(enable = "pclmulqdq,sse2,ssse3")]
+unsafe fn ghash(mut acc: __m128i, h: __m128i, data: &[u8]) -> __m128i {
+    let mask = load(&BSWAP_MASK);
+    let mut blocks = data.chunks_exact(16);
+    for block in &mut blocks {
+        let x = _mm_shuffle_epi8(_mm_loadu_si128(block.as_ptr() as *const __m128i), mask);
+        acc = gfmul(_mm_xor_si128(acc, x), h);
+    }
+    let rest = blocks.remainder();
+    if !rest.is_empty() {
+        let mut last = [0u8; 16];
+        last[..rest.len()].copy_from_slice(rest);
+        acc = gfmul(_mm_xor_si128(acc, _mm_shuffle_epi8(load(&last), mask)), h);
+    }
+    acc
+}
+
+/// XOR the keystream starting at counter block 2 into `data`.
+#[target_feature(enable = "aes,sse2")]
+unsafe fn ctr(keys: &[__m128i; 15], rounds: usize, base: __m128i, data: &mut [u8]) {
+    let mut n = 2u32;
+    let mut chunks = data.chunks_exact_mut(64);
+    for chunk in &mut chunks {
+        let mut blocks = [
+            counter(base, n),
+            counter(base, n.wrapping_add(1)),
+            counter(base, n.wrapping_add(2)),
+            counter(base, n.wrapping_add(3)),
+        ];
+        encrypt4(keys, rounds, &mut blocks);
+        for (i, block) in blocks.iter().enumerate() {
+            let at = chunk.as_mut_ptr().add(i * 16) as *mut __m128i;
+            _mm_storeu_si128(at, _mm_xor_si128(_mm_loadu_si128(at), *block));
+        }
+        n = n.wrapping_add(4);
+    }
+    for chunk in chunks.into_remainder().chunks_mut(16) {
+        let stream = store(encrypt(keys, rounds, counter(base, n)));
+        for (byte, key) in chunk.iter_mut().zip(stream.iter()) {
+            *byte ^= key;
+        }
+        n = n.wrapping_add(1);
+    }
+}
+
+impl Gcm {
+    /// `None` unless `key` is 16 (AES-128) or 32 (AES-256) bytes.
+    ///
+    /// # Safety
+    /// The CPU must support AES-NI, PCLMULQDQ and SSSE3.
+    #[target_feature(enable = "aes,pclmulqdq,sse2,ssse3")]
+    pub unsafe fn new(key: &[u8]) -> Option<Self> {
+        let mut keys = [_mm_setzero_si128(); 15];
+        let rounds = if let Ok(key) = <&[u8; 16]>::try_from(key) {
+            expand128(key, &mut keys);
+            10
+        } else if let Ok(key) = <&[u8; 32]>::try_from(key) {
+            expand256(key, &mut keys);
+            14
+        } else {
+            return None;
+        };
+        let mut gcm = Gcm {
+            round_keys: [[0; 16]; 15],
+            rounds,
+            h: [0; 16],
+        };
+        for (slot, key) in gcm.round_keys.iter_mut().zip(keys.iter()) {
+            *slot = store(*key);
+        }
+        let h = encrypt(&keys, rounds, _mm_setzero_si128());
+        gcm.h = store(_mm_shuffle_epi8(h, load(&BSWAP_MASK)));
+        Some(gcm)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn keys(&self) -> [__m128i; 15] {
+        let mut keys = [_mm_setzero_si128(); 15];
+        for (key, bytes) in keys.iter_mut().zip(self.round_keys.iter()) {
+            *key = load(bytes);
+        }
+        keys
+    }
+
+    /// GHASH of `aad` and `ciphertext` masked with the first counter block.
+    #[target_feature(enable = "aes,pclmulqdq,sse2,ssse3")]
+    unsafe fn tag(&self, keys: &[__m128i; 15], base: __m128i, aad: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+        let h = load(&self.h);
+        let mut acc = ghash(_mm_setzero_si128(), h, aad);
+        acc = ghash(acc, h, ciphertext);
+        let mut lengths = [0u8; 16];
+        lengths[..8].copy_from_slice(&((aad.len() as u64) * 8).to_be_bytes());
+        lengths[8..].copy_from_slice(&((ciphertext.len() as u64) * 8).to_be_bytes());
+        acc = ghash(acc, h, &lengths);
+        let s = _mm_shuffle_epi8(acc, load(&BSWAP_MASK));
+        store(_mm_xor_si128(s, encrypt(keys, self.rounds, counter(base, 1))))
+    }
+
+    /// Encrypt `data` in place and return the tag.
+    ///
+    /// # Safety
+    /// As for [`Gcm::new`].
+    #[target_feature(enable = "aes,pclmulqdq,sse2,ssse3")]
+    pub unsafe fn seal(&self, nonce: &[u8; 12], aad: &[u8], data: &mut [u8]) -> [u8; 16] {
+        let keys = self.keys();
+        let mut block = [0u8; 16];
+        block[..12].copy_from_slice(nonce);
+        let base = load(&block);
+        ctr(&keys, self.rounds, base, data);
+        self.tag(&keys, base, aad, data)
+    }
+
+    /// Check `tag` and decrypt `data` in place. On a mismatch `data` is left
+    /// as it was and `false` comes back.
+    ///
+    /// # Safety
+    /// As for [`Gcm::new`].
+    #[target_feature(enable = "aes,pclmulqdq,sse2,ssse3")]
+    pub unsafe fn open(&self, nonce: &[u8; 12], aad: &[u8], data: &mut [u8], tag: &[u8; 16]) -> bool {
+        let keys = self.keys();
+        let mut block = [0u8; 16];
+        block[..12].copy_from_slice(nonce);
+        let base = load(&block);
+        let expected = self.tag(&keys, base, aad, data);
+        if !super::ct_eq(&expected, tag) {
+            return false;
+        }
+        ctr(&keys, self.rounds, base, data);
+        true
+    }
+}
@@ -0,0 +1,752 @@
+//! AES-GCM and SHA-256 for TLS, on the CPU's crypto instructions when it
+//! has them, plus HMAC/PBKDF2 and AES-XTS for the encrypted data partition.
+//!
+//! CPUID decides at boot: AES-NI with PCLMULQDQ and SSSE3 runs AES-GCM in
+//! `aesni`, the SHA extensions run SHA-256 in `shani`. Each hardware path must
+//! match the software one on a fixed input before it is used. Otherwise, or
+//! with `crypto.accel` set to false, the RustCrypto implementations do the work
+//! (fixsliced AES and software GHASH, both constant time).
+//!
+//! The kernel is built without SSE, so only user programs keep state in the
+//! XMM registers; the hardware paths save and restore them around each call.
+//! `provider` plugs both into rustls.
+
+pub mod aesni;
+pub mod provider;
+pub mod shani;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::arch::x86_64::{__cpuid, __cpuid_count};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aes::cipher::{BlockDecrypt, BlockEncrypt};
+use aes::{Aes128, Aes256};
+use aes_gcm::aead::{AeadInPlace, KeyInit};
+use aes_gcm::{Aes128Gcm, Aes256Gcm, Nonce, Tag};
+use sha2::digest::generic_array::GenericArray;
+
+use crate::config::ConfigValue;
+use crate::perf::format_rate;
+use crate::println;
+
+pub const ACCEL_KEY: &str = "crypto.accel";
+pub const GCM_TAG_LEN: usize = 16;
+const BENCH_DEFAULT_KIB: usize = 1024;
+const BENCH_MAX_KIB: usize = 16 * 1024;
+
+const SHA256_H0: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// What CPUID and CR4 say about the crypto instructions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CpuCrypto {
+    pub aes_ni: bool,
+    pub pclmulqdq: bool,
+    pub ssse3: bool,
+    pub sse41: bool,
+    pub sha_ni: bool,
+    /// CR4.OSFXSR: SSE enabled and `fxsave` usable.
+    pub os_sse: bool,
+}
+
+impl CpuCrypto {
+    fn aes(&self) -> bool {
+        self.aes_ni && self.pclmulqdq && self.ssse3 && self.os_sse
+    }
+
+    fn sha(&self) -> bool {
+        self.sha_ni && self.ssse3 && self.sse41 && self.os_sse
+    }
+}
+
+static mut CPU: CpuCrypto = CpuCrypto {
+    aes_ni: false,
+    pclmulqdq: false,
+    ssse3: false,
+    sse41: false,
+    sha_ni: false,
+    os_sse: false,
+};
+/// Hardware paths that passed the comparison against software.
+static AES_VERIFIED: AtomicBool = AtomicBool::new(false);
+static SHA_VERIFIED: AtomicBool = AtomicBool::new(false);
+static ACCEL_ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn detect() -> CpuCrypto {
+    let leaf1 = unsafe { __cpuid(1) };
+    let leaf7_ebx = if unsafe { __cpuid(0) }.eax >= 7 {
+        unsafe { __cpuid_count(7, 0) }.ebx
+    } else {
+        0
+    };
+    let cr4: u64;
+    unsafe {
+        core::arch::asm!("mov {}, cr4", out(reg) cr4, options(nomem, nostack, preserves_flags));
+    }
+    CpuCrypto {
+        aes_ni: leaf1.ecx & (1 << 25) != 0,
+        pclmulqdq: leaf1.ecx & (1 << 1) != 0,
+        ssse3: leaf1.ecx & (1 << 9) != 0,
+        sse41: leaf1.ecx & (1 << 19) != 0,
+        sha_ni: leaf7_ebx & (1 << 29) != 0,
+        os_sse: cr4 & (1 << 9) != 0,
+    }
+}
+
+pub fn cpu() -> CpuCrypto {
+    unsafe { CPU }
+}
+
+#[repr(C, align(16))]
+struct FxState([u8; 512]);
+
+/// Run `f`, which uses SSE, with the interrupted program's x87/SSE state
+/// saved around it.
+fn with_simd<R>(f: impl FnOnce() -> R) -> R {
+    let mut saved = FxState([0; 512]);
+    unsafe {
+        core::arch::asm!("fxsave64 [{}]", in(reg) saved.0.as_mut_ptr(), options(nostack, preserves_flags));
+    }
+    let out = f();
+    unsafe {
+        core::arch::asm!("fxrstor64 [{}]", in(reg) saved.0.as_ptr(), options(nostack, preserves_flags));
+    }
+    out
+}
+
+/// Compare without an early exit, so the time says nothing about where a
+/// tag differs.
+pub(crate) fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Hardware,
+    Software,
+}
+
+impl Backend {
+    pub fn label(self) -> &'static str {
+        match self {
+            Backend::Hardware => "hardware",
+            Backend::Software => "software",
+        }
+    }
+}
+
+pub fn aes_backend() -> Backend {
+    if ACCEL_ENABLED.load(Ordering::Relaxed) && AES_VERIFIED.load(Ordering::Relaxed) {
+        Backend::Hardware
+    } else {
+        Backend::Software
+    }
+}
+
+pub fn sha_backend() -> Backend {
+    if ACCEL_ENABLED.load(Ordering::Relaxed) && SHA_VERIFIED.load(Ordering::Relaxed) {
+        Backend::Hardware
+    } else {
+        Backend::Software
+    }
+}
+
+enum Cipher {
+    Hardware(Box<aesni::Gcm>),
+    Aes128(Box<Aes128Gcm>),
+    Aes256(Box<Aes256Gcm>),
+}
+
+/// AES-128-GCM or AES-256-GCM with a 96-bit nonce.
+pub struct AesGcm(Cipher);
+
+impl AesGcm {
+    /// `None` unless `key` is 16 or 32 bytes.
+    pub fn new(key: &[u8]) -> Option<Self> {
+        Self::with_backend(key, aes_backend())
+    }
+
+    /// Asking for hardware on a CPU without it gives software.
+    pub fn with_backend(key: &[u8], backend: Backend) -> Option<Self> {
+        if backend == Backend::Hardware && cpu().aes() {
+            return with_simd(|| unsafe { aesni::Gcm::new(key) }).map(|gcm| AesGcm(Cipher::Hardware(Box::new(gcm))));
+        }
+        match key.len() {
+            16 => Aes128Gcm::new_from_slice(key)
+                .ok()
+                .map(|c| AesGcm(Cipher::Aes128(Box::new(c)))),
+            32 => Aes256Gcm::new_from_slice(key)
+                .ok()
+                .map(|c| AesGcm(Cipher::Aes256(Box::new(c)))),
+            _ => None,
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        match self.0 {
+            Cipher::Hardware(_) => Backend::Hardware,
+            _ => Backend::Software,
+        }
+    }
+
+    /// Encrypt `data` in place and return the tag.
+    pub fn seal_in_place(&self, nonce: &[u8; 12], aad: &[u8], data: &mut [u8]) -> Option<[u8; GCM_TAG_LEN]> {
+        let tag = match &self.0 {
+            Cipher::Hardware(gcm) => return Some(with_simd(|| unsafe { gcm.seal(nonce, aad, data) })),
+            Cipher::Aes128(c) => c.encrypt_in_place_detached(Nonce::from_slice(nonce), aad, data),
+            Cipher::Aes256(c) => c.encrypt_in_place_detached(Nonce::from_slice(nonce), aad, data),
+        }
+        .ok()?;
+        let mut out = [0u8; GCM_TAG_LEN];
+        out.copy_from_slice(&tag);
+        Some(out)
+    }
+
+    /// Check `tag` and decrypt `data` in place; `false` when it does not
+    /// authenticate.
+    pub fn open_in_place(&self, nonce: &[u8; 12], aad: &[u8], data: &mut [u8], tag: &[u8; GCM_TAG_LEN]) -> bool {
+        match &self.0 {
+            Cipher::Hardware(gcm) => with_simd(|| unsafe { gcm.open(nonce, aad, data, tag) }),
+            Cipher::Aes128(c) => c
+                .decrypt_in_place_detached(Nonce::from_slice(nonce), aad, data, Tag::from_slice(tag))
+                .is_ok(),
+            Cipher::Aes256(c) => c
+                .decrypt_in_place_detached(Nonce::from_slice(nonce), aad, data, Tag::from_slice(tag))
+                .is_ok(),
+        }
+    }
+}
+
+fn sha256_compress(backend: Backend, state: &mut [u32; 8], blocks: &[u8]) {
+    match backend {
+        Backend::Hardware => with_simd(|| unsafe { shani::compress(state, blocks) }),
+        Backend::Software => {
+            for block in blocks.chunks_exact(64) {
+                sha2::compress256(state, core::slice::from_ref(GenericArray::from_slice(block)));
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    buffer: [u8; 64],
+    buffered: usize,
+    length: u64,
+    backend: Backend,
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sha256 {
+    pub fn new() -> Self {
+        Self::with_backend(sha_backend())
+    }
+
+    /// Asking for hardware on a CPU without it gives software.
+    pub fn with_backend(backend: Backend) -> Self {
+        Self {
+            state: SHA256_H0,
+            buffer: [0; 64],
+            buffered: 0,
+            length: 0,
+            backend: if cpu().sha() { backend } else { Backend::Software },
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.length = self.length.wrapping_add(data.len() as u64);
+        if self.buffered > 0 {
+            let take = (64 - self.buffered).min(data.len());
+            self.buffer[self.buffered..self.buffered + take].copy_from_slice(&data[..take]);
+            self.buffered += take;
+            data = &data[take..];
+            if self.buffered < 64 {
+                return;
+            }
+            let block = self.buffer;
+            sha256_compress(self.backend, &mut self.state, &block);
+            self.buffered = 0;
+        }
+        let whole = data.len() - data.len() % 64;
+        if whole > 0 {
+            sha256_compress(self.backend, &mut self.state, &data[..whole]);
+        }
+        let rest = &data[whole..];
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.buffered = rest.len();
+    }
+
+    pub fn finish(mut self) -> [u8; 32] {
+        let bits = self.length.wrapping_mul(8);
+        let mut padding = [0u8; 64];
+        padding[0] = 0x80;
+        let pad_len = if self.buffered < 56 {
+            56 - self.buffered
+        } else {
+            120 - self.buffered
+        };
+        self.update(&padding[..pad_len]);
+        self.update(&bits.to_be_bytes());
+        let mut out = [0u8; 32];
+        for (chunk, word) in out.chunks_exact_mut(4).zip(self.state.iter()) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        out
+    }
+
+    pub fn digest(data: &[u8]) -> [u8; 32] {
+        let mut hasher = Self::new();
+        hasher.update(data);
+        hasher.finish()
+    }
+}
+
+/// HMAC-SHA256 (RFC 2104).
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > 64 {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = Sha256::new();
+    inner.update(&block.map(|b| b ^ 0x36));
+    inner.update(data);
+    let mut outer = Sha256::new();
+    outer.update(&block.map(|b| b ^ 0x5c));
+    outer.update(&inner.finish());
+    outer.finish()
+}
+
+/// PBKDF2 with HMAC-SHA256 (RFC 8018), filling `out`.
+pub fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32, out: &mut [u8]) {
+    for (index, chunk) in out.chunks_mut(32).enumerate() {
+        let mut first = Vec::with_capacity(salt.len() + 4);
+        first.extend_from_slice(salt);
+        first.extend_from_slice(&(index as u32 + 1).to_be_bytes());
+        let mut u = hmac_sha256(password, &first);
+        let mut t = u;
+        for _ in 1..iterations {
+            u = hmac_sha256(password, &u);
+            for (acc, byte) in t.iter_mut().zip(u.iter()) {
+                *acc ^= byte;
+            }
+        }
+        chunk.copy_from_slice(&t[..chunk.len()]);
+    }
+}
+
+#[derive(Clone)]
+enum XtsKeys {
+    Aes128(Aes128, Aes128),
+    Aes256(Aes256, Aes256),
+}
+
+/// AES-XTS (IEEE 1619) over 512-byte sectors, tweaked by the sector number.
+/// Software AES only: the kernel has no XMM state to spare outside the
+/// GCM path.
+#[derive(Clone)]
+pub struct AesXts(XtsKeys);
+
+impl AesXts {
+    /// Data key followed by tweak key: 32 bytes for AES-128-XTS, 64 for
+    /// AES-256-XTS.
+    pub fn new(key: &[u8]) -> Option<Self> {
+        let half = key.len() / 2;
+        let keys = match key.len() {
+            32 => XtsKeys::Aes128(
+                Aes128::new_from_slice(&key[..half]).ok()?,
+                Aes128::new_from_slice(&key[half..]).ok()?,
+            ),
+            64 => XtsKeys::Aes256(
+                Aes256::new_from_slice(&key[..half]).ok()?,
+                Aes256::new_from_slice(&key[half..]).ok()?,
+            ),
+            _ => return None,
+        };
+        Some(Self(keys))
+    }
+
+    pub fn encrypt_sector(&self, sector: u64, data: &mut [u8]) {
+        match &self.0 {
+            XtsKeys::Aes128(data_key, tweak_key) => xts(data_key, tweak_key, sector, data, true),
+            XtsKeys::Aes256(data_key, tweak_key) => xts(data_key, tweak_key, sector, data, true),
+        }
+    }
+
+    pub fn decrypt_sector(&self, sector: u64, data: &mut [u8]) {
+        match &self.0 {
+            XtsKeys::Aes128(data_key, tweak_key) => xts(data_key, tweak_key, sector, data, false),
+            XtsKeys::Aes256(data_key, tweak_key) => xts(data_key, tweak_key, sector, data, false),
+        }
+    }
+}
+
+/// One data unit; a trailing partial block is left alone, sectors never
+/// have one.
+fn xts<C: BlockEncrypt + BlockDecrypt>(data_key: &C, tweak_key: &C, sector: u64, data: &mut [u8], encrypt: bool) {
+    let mut tweak = GenericArray::from([0u8; 16]);
+    tweak[..8].copy_from_slice(&sector.to_le_bytes());
+    tweak_key.encrypt_block(&mut tweak);
+    for chunk in data.chunks_exact_mut(16) {
+        let mut block = GenericArray::from([0u8; 16]);
+        for (out, (byte, t)) in block.iter_mut().zip(chunk.iter().zip(tweak.iter())) {
+            *out = byte ^ t;
+        }
+        if encrypt {
+            data_key.encrypt_block(&mut block);
+        } else {
+            data_key.decrypt_block(&mut block);
+        }
+        for (out, (byte, t)) in chunk.iter_mut().zip(block.iter().zip(tweak.iter())) {
+            *out = byte ^ t;
+        }
+        // Multiply the tweak by x in GF(2^128), little-endian.
+        let mut carry = 0u8;
+        for byte in tweak.iter_mut() {
+            let next = *byte >> 7;
+            *byte = (*byte << 1) | carry;
+            carry = next;
+        }
+        if carry != 0 {
+            tweak[0] ^= 0x87;
+        }
+    }
+}
+
+fn test_pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i.wrapping_mul(31) ^ (i >> 8)) as u8).collect()
+}
+
+/// Hardware AES-GCM agrees with software, both key sizes, on a message that
+/// covers the four-block path, a partial block and AAD.
+fn aes_hardware_matches() -> bool {
+    let aad = test_pattern(20);
+    [16usize, 32].iter().all(|&key_len| {
+        let key = test_pattern(key_len + 7);
+        let (Some(hw), Some(sw)) = (
+            AesGcm::with_backend(&key[7..], Backend::Hardware),
+            AesGcm::with_backend(&key[7..], Backend::Software),
+        ) else {
+            return false;
+        };
+        let nonce = [0x5a; 12];
+        let mut hw_data = test_pattern(151);
+        let mut sw_data = hw_data.clone();
+        let hw_tag = hw.seal_in_place(&nonce, &aad, &mut hw_data);
+        let sw_tag = sw.seal_in_place(&nonce, &aad, &mut sw_data);
+        hw.backend() == Backend::Hardware
+            && hw_tag.is_some()
+            && hw_tag == sw_tag
+            && hw_data == sw_data
+            && sw_tag.is_some_and(|tag| hw.open_in_place(&nonce, &aad, &mut hw_data, &tag))
+            && hw_data == test_pattern(151)
+    })
+}
+
+fn sha_hardware_matches() -> bool {
+    let data = test_pattern(300);
+    let digest = |backend| {
+        let mut hasher = Sha256::with_backend(backend);
+        hasher.update(&data[..7]);
+        hasher.update(&data[7..]);
+        hasher.finish()
+    };
+    Sha256::with_backend(Backend::Hardware).backend() == Backend::Hardware
+        && digest(Backend::Hardware) == digest(Backend::Software)
+}
+
+fn on_accel_changed(_key: &str, value: Option<&ConfigValue>) {
+    let enabled = match value {
+        Some(ConfigValue::Bool(v)) => *v,
+        Some(ConfigValue::Int(v)) => *v != 0,
+        _ => true,
+    };
+    ACCEL_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Detect the instructions, check the hardware paths and follow
+/// `crypto.accel`.
+pub fn init() {
+    let cpu = detect();
+    unsafe {
+        CPU = cpu;
+    }
+    AES_VERIFIED.store(cpu.aes() && aes_hardware_matches(), Ordering::Relaxed);
+    SHA_VERIFIED.store(cpu.sha() && sha_hardware_matches(), Ordering::Relaxed);
+    crate::config::watch(ACCEL_KEY, on_accel_changed);
+    on_accel_changed(ACCEL_KEY, crate::config::get(ACCEL_KEY).as_ref());
+    for line in status_lines().iter() {
+        println(alloc::format!("Crypto: {}", line).as_str());
+    }
+    if cpu.aes() && !AES_VERIFIED.load(Ordering::Relaxed) {
+        println("Crypto: AES-NI no coincide con software, se usa software");
+    }
+    if cpu.sha() && !SHA_VERIFIED.load(Ordering::Relaxed) {
+        println("Crypto: SHA-NI no coincide con software, se usa software");
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "si"
+    } else {
+        "no"
+    }
+}
+
+pub fn status_lines() -> Vec<String> {
+    let cpu = cpu();
+    alloc::vec![
+        alloc::format!(
+            "CPU aes-ni {}, pclmulqdq {}, ssse3 {}, sse4.1 {}, sha-ni {}, SSE activado {}",
+            yes_no(cpu.aes_ni),
+            yes_no(cpu.pclmulqdq),
+            yes_no(cpu.ssse3),
+            yes_no(cpu.sse41),
+            yes_no(cpu.sha_ni),
+            yes_no(cpu.os_sse)
+        ),
+        alloc::format!(
+            "AES-GCM {}, SHA-256 {} ({} {})",
+            aes_backend().label(),
+            sha_backend().label(),
+            ACCEL_KEY,
+            yes_no(ACCEL_ENABLED.load(Ordering::Relaxed))
+        ),
+    ]
+}
+
+/// Bytes per second for `bytes` processed in `us` microseconds.
+fn rate(bytes: usize, us: u64) -> u64 {
+    (bytes as u64).saturating_mul(1_000_000) / us.max(1)
+}
+
+fn speedup(hardware: u64, software: u64) -> String {
+    let tenths = hardware.saturating_mul(10) / software.max(1);
+    alloc::format!("x{}.{}", tenths / 10, tenths % 10)
+}
+
+/// Time `run` over `len` bytes and return (rate, output).
+fn time<T>(len: usize, run: impl FnOnce() -> T) -> (u64, T) {
+    let start = crate::perf::now_us();
+    let out = run();
+    (rate(len, crate::perf::now_us().saturating_sub(start)), out)
+}
+
+fn bench_aes(out: &mut Vec<String>, key_len: usize, data: &[u8]) {
+    let name = alloc::format!("AES-{}-GCM", key_len * 8);
+    let key = test_pattern(key_len);
+    let nonce = [0x24; 12];
+    let mut results = Vec::new();
+    for backend in [Backend::Software, Backend::Hardware] {
+        let Some(cipher) = AesGcm::with_backend(&key, backend) else {
+            continue;
+        };
+        if cipher.backend() != backend {
+            out.push(alloc::format!("{:<12} {:<9} no disponible", name, backend.label()));
+            continue;
+        }
+        let mut buffer = data.to_vec();
+        let (rate, tag) = time(data.len(), || cipher.seal_in_place(&nonce, b"cryptobench", &mut buffer));
+        results.push((rate, tag, buffer));
+        let speed = match results.as_slice() {
+            [software, hardware] => alloc::format!("  {}", speedup(hardware.0, software.0)),
+            _ => String::new(),
+        };
+        out.push(alloc::format!(
+            "{:<12} {:<9} {:>14}{}",
+            name,
+            backend.label(),
+            format_rate(rate),
+            speed
+        ));
+    }
+    if let [software, hardware] = results.as_slice() {
+        let same = software.1 == hardware.1 && software.2 == hardware.2;
+        out.push(alloc::format!("{:<12} resultados iguales: {}", name, yes_no(same)));
+    }
+}
+
+fn bench_sha(out: &mut Vec<String>, data: &[u8]) {
+    let mut results: Vec<(u64, [u8; 32])> = Vec::new();
+    for backend in [Backend::Software, Backend::Hardware] {
+        let mut hasher = Sha256::with_backend(backend);
+        if hasher.backend() != backend {
+            out.push(alloc::format!("{:<12} {:<9} no disponible", "SHA-256", backend.label()));
+            continue;
+        }
+        let (rate, digest) = time(data.len(), || {
+            hasher.update(data);
+            hasher.finish()
+        });
+        results.push((rate, digest));
+        let speed = match results.as_slice() {
+            [software, hardware] => alloc::format!("  {}", speedup(hardware.0, software.0)),
+            _ => String::new(),
+        };
+        out.push(alloc::format!(
+            "{:<12} {:<9} {:>14}{}",
+            "SHA-256",
+            backend.label(),
+            format_rate(rate),
+            speed
+        ));
+    }
+    if let [software, hardware] = results.as_slice() {
+        out.push(alloc::format!(
+            "{:<12} resultados iguales: {}",
+            "SHA-256",
+            yes_no(software.1 == hardware.1)
+        ));
+    }
+}
+
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let kib = match args.trim() {
+        "" => BENCH_DEFAULT_KIB,
+        text => match text.parse::<usize>() {
+            Ok(kib) if (1..=BENCH_MAX_KIB).contains(&kib) => kib,
+            _ => {
+                out.push(alloc::format!("Uso: cryptobench [KiB, 1 a {}]", BENCH_MAX_KIB));
+                return out;
+            }
+        },
+    };
+    for line in status_lines() {
+        out.push(alloc::format!("cryptobench: {}", line));
+    }
+    out.push(alloc::format!("cryptobench: {} KiB por prueba", kib));
+    let data = test_pattern(kib * 1024);
+    bench_aes(&mut out, 16, &data);
+    bench_aes(&mut out, 32, &data);
+    bench_sha(&mut out, &data);
+    out
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| alloc::format!("{:02x}", b)).collect()
+}
+
+crate::selftest::kernel_tests! {
+    "crypto";
+
+    fn aes_gcm_nist_vectors() {
+        // GCM spec test cases 2 and 14: zero key and IV, one zero block.
+        let cases: [(usize, &str, &str); 2] = [
+            (16, "0388dace60b6a392f328c2b971b2fe78", "ab6e47d42cec13bdf53a67b21257bddf"),
+            (32, "cea7403d4d606b6e074ec5d3baf39d18", "d0d1c8a799996bf0265b98b5d48ab919"),
+        ];
+        for backend in [Backend::Software, Backend::Hardware] {
+            for (key_len, ciphertext, tag) in cases.iter() {
+                let key = alloc::vec![0u8; *key_len];
+                let cipher = AesGcm::with_backend(&key, backend).ok_or_else(|| String::from("clave"))?;
+                let mut data = [0u8; 16];
+                let sealed = cipher.seal_in_place(&[0; 12], &[], &mut data).ok_or_else(|| String::from("cifrado"))?;
+                crate::selftest::ensure_eq(hex(&data).as_str(), *ciphertext, "texto cifrado")?;
+                crate::selftest::ensure_eq(hex(&sealed).as_str(), *tag, "etiqueta")?;
+                crate::selftest::ensure(cipher.open_in_place(&[0; 12], &[], &mut data, &sealed), "abre")?;
+                crate::selftest::ensure_eq(data, [0u8; 16], "descifrado")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn aes_gcm_rejects_bad_tag() {
+        let cipher = AesGcm::new(&[7u8; 16]).ok_or_else(|| String::from("clave"))?;
+        let mut data = test_pattern(70);
+        let mut tag = cipher.seal_in_place(&[1; 12], b"aad", &mut data).ok_or_else(|| String::from("cifrado"))?;
+        let sealed = data.clone();
+        tag[15] ^= 1;
+        crate::selftest::ensure(!cipher.open_in_place(&[1; 12], b"aad", &mut data, &tag), "etiqueta mala")?;
+        crate::selftest::ensure(data == sealed, "datos intactos")?;
+        crate::selftest::ensure(AesGcm::new(&[0u8; 24]).is_none(), "AES-192 no soportado")
+    }
+
+    fn sha256_vectors() {
+        for backend in [Backend::Software, Backend::Hardware] {
+            let mut hasher = Sha256::with_backend(backend);
+            hasher.update(b"abc");
+            crate::selftest::ensure_eq(
+                hex(&hasher.finish()).as_str(),
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                "abc",
+            )?;
+            let mut hasher = Sha256::with_backend(backend);
+            for part in b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq".chunks(5) {
+                hasher.update(part);
+            }
+            crate::selftest::ensure_eq(
+                hex(&hasher.finish()).as_str(),
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+                "448 bits por partes",
+            )?;
+        }
+        Ok(())
+    }
+
+    fn hmac_and_pbkdf2_vectors() {
+        // RFC 4231 test case 2, and PBKDF2-HMAC-SHA256 with one and two
+        // iterations.
+        crate::selftest::ensure_eq(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")).as_str(),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            "hmac",
+        )?;
+        let mut key = [0u8; 32];
+        pbkdf2_sha256(b"password", b"salt", 1, &mut key);
+        crate::selftest::ensure_eq(
+            hex(&key).as_str(),
+            "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b",
+            "pbkdf2 c=1",
+        )?;
+        pbkdf2_sha256(b"password", b"salt", 2, &mut key);
+        crate::selftest::ensure_eq(
+            hex(&key).as_str(),
+            "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43",
+            "pbkdf2 c=2",
+        )
+    }
+
+    fn aes_xts_vectors() {
+        // IEEE 1619 vector 1: zero keys, data unit 0, 32 zero bytes.
+        let xts = AesXts::new(&[0u8; 32]).ok_or_else(|| String::from("clave"))?;
+        let mut data = [0u8; 32];
+        xts.encrypt_sector(0, &mut data);
+        crate::selftest::ensure_eq(
+            hex(&data).as_str(),
+            "917cf69ebd68b2ec9b9fe9a3eadda692cd43d2f59598ed858c02c2652fbf922e",
+            "vector 1",
+        )?;
+        let key = test_pattern(64);
+        let xts = AesXts::new(&key).ok_or_else(|| String::from("clave"))?;
+        let mut sector = test_pattern(512);
+        xts.encrypt_sector(7, &mut sector);
+        crate::selftest::ensure(sector != test_pattern(512), "sin cifrar")?;
+        let mut other = test_pattern(512);
+        xts.encrypt_sector(8, &mut other);
+        crate::selftest::ensure(sector != other, "tweak ignorado")?;
+        xts.decrypt_sector(7, &mut sector);
+        crate::selftest::ensure_eq(sector, test_pattern(512), "ida y vuelta")?;
+        crate::selftest::ensure(AesXts::new(&[0u8; 48]).is_none(), "clave de 48 bytes")
+    }
+
+    fn backends_agree() {
+        crate::selftest::ensure(!cpu().aes() || aes_hardware_matches(), "AES-NI igual a software")?;
+        crate::selftest::ensure(!cpu().sha() || sha_hardware_matches(), "SHA-NI igual a software")
+    }
+}
@@ -0,0 +1,231 @@
+//! rustls provider: rustls-rustcrypto with the TLS 1.3 AES-GCM suites moved
+//! onto `AesGcm` and SHA-256 (transcript, HMAC, HKDF) onto `Sha256`.
+//! ChaCha20-Poly1305, key exchange and signatures stay as they were. When
+//! AES runs in software ChaCha20 is offered first, since it is faster there.
+
+use alloc::boxed::Box;
+
+use rustls::crypto::cipher::{
+    make_tls13_aad, AeadKey, InboundOpaqueMessage, InboundPlainMessage, Iv, MessageDecrypter, MessageEncrypter, Nonce,
+    OutboundOpaqueMessage, OutboundPlainMessage, PrefixedPayload, Tls13AeadAlgorithm, UnsupportedOperationError,
+};
+use rustls::crypto::tls13::HkdfUsingHmac;
+use rustls::crypto::{hash, hmac, CipherSuiteCommon, CryptoProvider};
+use rustls::{
+    CipherSuite, ConnectionTrafficSecrets, ContentType, Error, ProtocolVersion, SupportedCipherSuite, Tls13CipherSuite,
+};
+
+use super::{AesGcm, Backend, Sha256, GCM_TAG_LEN};
+
+/// Records per key before rustls updates it (2^24 for AES-GCM).
+const GCM_CONFIDENTIALITY_LIMIT: u64 = 1 << 24;
+
+struct Sha256Hash;
+
+impl hash::Hash for Sha256Hash {
+    fn start(&self) -> Box<dyn hash::Context> {
+        Box::new(Sha256Context(Sha256::new()))
+    }
+
+    fn hash(&self, data: &[u8]) -> hash::Output {
+        hash::Output::new(&Sha256::digest(data))
+    }
+
+    fn output_len(&self) -> usize {
+        32
+    }
+
+    fn algorithm(&self) -> hash::HashAlgorithm {
+        hash::HashAlgorithm::SHA256
+    }
+}
+
+struct Sha256Context(Sha256);
+
+impl hash::Context for Sha256Context {
+    fn fork_finish(&self) -> hash::Output {
+        hash::Output::new(&self.0.clone().finish())
+    }
+
+    fn fork(&self) -> Box<dyn hash::Context> {
+        Box::new(Sha256Context(self.0.clone()))
+    }
+
+    fn finish(self: Box<Self>) -> hash::Output {
+        hash::Output::new(&self.0.finish())
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+}
+
+struct HmacSha256;
+
+impl hmac::Hmac for HmacSha256 {
+    fn with_key(&self, key: &[u8]) -> Box<dyn hmac::Key> {
+        let mut block = [0u8; 64];
+        if key.len() > block.len() {
+            block[..32].copy_from_slice(&Sha256::digest(key));
+        } else {
+            block[..key.len()].copy_from_slice(key);
+        }
+        let mut inner = Sha256::new();
+        let mut outer = Sha256::new();
+        inner.update(&block.map(|b| b ^ 0x36));
+        outer.update(&block.map(|b| b ^ 0x5c));
+        Box::new(HmacSha256Key { inner, outer })
+    }
+
+    fn hash_output_len(&self) -> usize {
+        32
+    }
+}
+
+/// Hash states with the padded key already absorbed.
+struct HmacSha256Key {
+    inner: Sha256,
+    outer: Sha256,
+}
+
+impl hmac::Key for HmacSha256Key {
+    fn sign_concat(&self, first: &[u8], middle: &[&[u8]], last: &[u8]) -> hmac::Tag {
+        let mut inner = self.inner.clone();
+        inner.update(first);
+        for part in middle.iter() {
+            inner.update(part);
+        }
+        inner.update(last);
+        let mut outer = self.outer.clone();
+        outer.update(&inner.finish());
+        hmac::Tag::new(&outer.finish())
+    }
+
+    fn tag_len(&self) -> usize {
+        32
+    }
+}
+
+/// TLS 1.3 AES-GCM; the field is the key length in bytes.
+struct Tls13AesGcm(usize);
+
+impl Tls13AeadAlgorithm for Tls13AesGcm {
+    fn encrypter(&self, key: AeadKey, iv: Iv) -> Box<dyn MessageEncrypter> {
+        Box::new(AesGcmRecords::new(key, iv))
+    }
+
+    fn decrypter(&self, key: AeadKey, iv: Iv) -> Box<dyn MessageDecrypter> {
+        Box::new(AesGcmRecords::new(key, iv))
+    }
+
+    fn key_len(&self) -> usize {
+        self.0
+    }
+
+    fn extract_keys(&self, key: AeadKey, iv: Iv) -> Result<ConnectionTrafficSecrets, UnsupportedOperationError> {
+        Ok(match self.0 {
+            16 => ConnectionTrafficSecrets::Aes128Gcm { key, iv },
+            _ => ConnectionTrafficSecrets::Aes256Gcm { key, iv },
+        })
+    }
+}
+
+struct AesGcmRecords {
+    cipher: AesGcm,
+    iv: Iv,
+}
+
+impl AesGcmRecords {
+    fn new(key: AeadKey, iv: Iv) -> Self {
+        Self {
+            // rustls hands over `key_len` bytes.
+            cipher: AesGcm::new(key.as_ref()).unwrap(),
+            iv,
+        }
+    }
+}
+
+impl MessageEncrypter for AesGcmRecords {
+    fn encrypt(&mut self, msg: OutboundPlainMessage<'_>, seq: u64) -> Result<OutboundOpaqueMessage, Error> {
+        let total_len = self.encrypted_payload_len(msg.payload.len());
+        let mut payload = PrefixedPayload::with_capacity(total_len);
+        payload.extend_from_chunks(&msg.payload);
+        payload.extend_from_slice(&msg.typ.to_array());
+        let nonce = Nonce::new(&self.iv, seq).0;
+        let tag = self
+            .cipher
+            .seal_in_place(&nonce, &make_tls13_aad(total_len), payload.as_mut())
+            .ok_or(Error::EncryptError)?;
+        payload.extend_from_slice(&tag);
+        Ok(OutboundOpaqueMessage::new(
+            ContentType::ApplicationData,
+            ProtocolVersion::TLSv1_2,
+            payload,
+        ))
+    }
+
+    fn encrypted_payload_len(&self, payload_len: usize) -> usize {
+        payload_len + 1 + GCM_TAG_LEN
+    }
+}
+
+impl MessageDecrypter for AesGcmRecords {
+    fn decrypt<'a>(&mut self, mut msg: InboundOpaqueMessage<'a>, seq: u64) -> Result<InboundPlainMessage<'a>, Error> {
+        let payload = &mut msg.payload;
+        let body_len = payload.len().checked_sub(GCM_TAG_LEN).ok_or(Error::DecryptError)?;
+        let aad = make_tls13_aad(payload.len());
+        let nonce = Nonce::new(&self.iv, seq).0;
+        let (body, tag) = payload.split_at_mut(body_len);
+        let tag: &[u8; GCM_TAG_LEN] = (&*tag).try_into().map_err(|_| Error::DecryptError)?;
+        if !self.cipher.open_in_place(&nonce, &aad, body, tag) {
+            return Err(Error::DecryptError);
+        }
+        payload.truncate(body_len);
+        msg.into_tls13_unpadded_message()
+    }
+}
+
+const fn tls13(suite: SupportedCipherSuite) -> &'static Tls13CipherSuite {
+    match suite {
+        SupportedCipherSuite::Tls13(suite) => suite,
+        _ => panic!("not a TLS 1.3 suite"),
+    }
+}
+
+static TLS13_AES_128_GCM_SHA256: Tls13CipherSuite = Tls13CipherSuite {
+    common: CipherSuiteCommon {
+        suite: CipherSuite::TLS13_AES_128_GCM_SHA256,
+        hash_provider: &Sha256Hash,
+        confidentiality_limit: GCM_CONFIDENTIALITY_LIMIT,
+    },
+    hkdf_provider: &HkdfUsingHmac(&HmacSha256),
+    aead_alg: &Tls13AesGcm(16),
+    quic: None,
+};
+
+/// SHA-384 stays in rustls-rustcrypto.
+static TLS13_AES_256_GCM_SHA384: Tls13CipherSuite = Tls13CipherSuite {
+    common: CipherSuiteCommon {
+        suite: CipherSuite::TLS13_AES_256_GCM_SHA384,
+        hash_provider: tls13(rustls_rustcrypto::TLS13_AES_256_GCM_SHA384).common.hash_provider,
+        confidentiality_limit: GCM_CONFIDENTIALITY_LIMIT,
+    },
+    hkdf_provider: tls13(rustls_rustcrypto::TLS13_AES_256_GCM_SHA384).hkdf_provider,
+    aead_alg: &Tls13AesGcm(32),
+    quic: None,
+};
+
+/// The provider every kernel TLS client uses.
+pub fn tls_provider() -> CryptoProvider {
+    let aes = [
+        SupportedCipherSuite::Tls13(&TLS13_AES_128_GCM_SHA256),
+        SupportedCipherSuite::Tls13(&TLS13_AES_256_GCM_SHA384),
+    ];
+    let chacha = rustls_rustcrypto::TLS13_CHACHA20_POLY1305_SHA256;
+    let mut provider = rustls_rustcrypto::provider();
+    provider.cipher_suites = match super::aes_backend() {
+        Backend::Hardware => alloc::vec![aes[0], aes[1], chacha],
+        Backend::Software => alloc::vec![chacha, aes[0], aes[1]],
+    };
+    provider
+}
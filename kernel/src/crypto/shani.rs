@@ -0,0 +1,78 @@
+//! SHA-256 compression on the SHA extensions (`sha256rnds2`,
+//! `sha256msg1/2`). Needs SHA, SSSE3 and SSE4.1, and runs inside
+//! `super::with_simd`.
+
+use core::arch::x86_64::*;
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5, 0xd807aa98,
+    0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786,
+    0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8,
+    0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819,
+    0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a,
+    0x5b9cca4f, 0x682e6ff3, 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+    0xc67178f2,
+];
+
+/// Big-endian words of a message block, per 32-bit lane.
+const WORD_MASK: [u8; 16] = [3, 2, 1, 0, 7, 6, 5, 4, 11, 10, 9, 8, 15, 14, 13, 12];
+
+/// Run the 64-byte `blocks` through the compression function.
+///
+/// # Safety
+/// The CPU must support SHA, SSSE3 and SSE4.1; `blocks.len()` is a multiple
+/// of 64.
+#[target_feature(enable = "sha,sse2,ssse3,sse4.1")]
+pub unsafe fn compress(state: &mut [u32; 8], blocks: &[u8]) {
+    let mask = _mm_loadu_si128(WORD_MASK.as_ptr() as *const __m128i);
+    let state_ptr = state.as_ptr() as *const __m128i;
+    let dcba = _mm_shuffle_epi32(_mm_loadu_si128(state_ptr), 0xb1);
+    let hgfe = _mm_shuffle_epi32(_mm_loadu_si128(state_ptr.add(1)), 0x1b);
+    // The instructions want the state as ABEF and CDGH.
+    let mut abef = _mm_alignr_epi8(dcba, hgfe, 8);
+    let mut cdgh = _mm_blend_epi16(hgfe, dcba, 0xf0);
+
+    macro_rules! rounds4 {
+        ($w:expr, $i:expr) => {{
+            let wk = _mm_add_epi32($w, _mm_loadu_si128(K.as_ptr().add($i * 4) as *const __m128i));
+            cdgh = _mm_sha256rnds2_epu32(cdgh, abef, wk);
+            abef = _mm_sha256rnds2_epu32(abef, cdgh, _mm_shuffle_epi32(wk, 0x0e));
+        }};
+    }
+
+    for block in blocks.chunks_exact(64) {
+        let abef_save = abef;
+        let cdgh_save = cdgh;
+        let ptr = block.as_ptr() as *const __m128i;
+        let mut w = [
+            _mm_shuffle_epi8(_mm_loadu_si128(ptr), mask),
+            _mm_shuffle_epi8(_mm_loadu_si128(ptr.add(1)), mask),
+            _mm_shuffle_epi8(_mm_loadu_si128(ptr.add(2)), mask),
+            _mm_shuffle_epi8(_mm_loadu_si128(ptr.add(3)), mask),
+        ];
+        for (i, word) in w.iter().enumerate() {
+            rounds4!(*word, i);
+        }
+        for i in 4..16 {
+            // W[i] from W[i-4] .. W[i-1], kept in a ring of four.
+            let next = _mm_sha256msg2_epu32(
+                _mm_add_epi32(
+                    _mm_sha256msg1_epu32(w[i % 4], w[(i + 1) % 4]),
+                    _mm_alignr_epi8(w[(i + 3) % 4], w[(i + 2) % 4], 4),
+                ),
+                w[(i + 3) % 4],
+            );
+            w[i % 4] = next;
+            rounds4!(next, i);
+        }
+        abef = _mm_add_epi32(abef, abef_save);
+        cdgh = _mm_add_epi32(cdgh, cdgh_save);
+    }
+
+    let feba = _mm_shuffle_epi32(abef, 0x1b);
+    let dchg = _mm_shuffle_epi32(cdgh, 0xb1);
+    let out = state.as_mut_ptr() as *mut __m128i;
+    _mm_storeu_si128(out, _mm_blend_epi16(feba, dchg, 0xf0));
+    _mm_storeu_si128(out.add(1), _mm_alignr_epi8(dchg, feba, 8));
+}
@@ -0,0 +1,401 @@
+//! Device model: one tree of every device the kernel knows about.
+//!
+//! Buses and controllers are nodes; drivers add the devices they expose
+//! (network interfaces, disks, input devices, sound cards) as children of
+//! the PCI function they bound, with a class (`net`, `block`, `input`,
+//! `sound`, `graphics`) and named attributes. An attribute is either a fixed
+//! text or a function read on every access, so a link state or a screen
+//! mode is never stale. `pci::scan` keeps the `pci0000:00` subtree in sync
+//! with the bus. `sysfs` shows the tree under `/sys` and `devices` prints it.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::spinlock::SpinLock;
+
+pub const PCI_ROOT: &str = "pci0000:00";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DeviceId(u32);
+
+#[derive(Clone)]
+pub enum Attr {
+    Text(String),
+    Live(fn() -> String),
+}
+
+struct Node {
+    id: DeviceId,
+    parent: Option<DeviceId>,
+    name: String,
+    class: Option<&'static str>,
+    driver: Option<&'static str>,
+    attrs: Vec<(&'static str, Attr)>,
+}
+
+struct Tree {
+    nodes: Vec<Node>,
+    next_id: u32,
+}
+
+impl Tree {
+    fn get(&self, id: DeviceId) -> Option<&Node> {
+        self.nodes.iter().find(|n| n.id == id)
+    }
+
+    fn get_mut(&mut self, id: DeviceId) -> Option<&mut Node> {
+        self.nodes.iter_mut().find(|n| n.id == id)
+    }
+
+    fn child(&self, parent: Option<DeviceId>, name: &str) -> Option<DeviceId> {
+        self.nodes
+            .iter()
+            .find(|n| n.parent == parent && n.name == name)
+            .map(|n| n.id)
+    }
+
+    fn path(&self, id: DeviceId) -> String {
+        let mut parts = Vec::new();
+        let mut cursor = self.get(id);
+        while let Some(node) = cursor {
+            parts.push(node.name.as_str());
+            cursor = node.parent.and_then(|p| self.get(p));
+        }
+        parts.reverse();
+        parts.join("/")
+    }
+}
+
+static TREE: SpinLock<Tree> = SpinLock::new(Tree {
+    nodes: Vec::new(),
+    next_id: 1,
+});
+
+/// A node as seen from outside, with live attributes already read.
+#[derive(Clone, Debug)]
+pub struct Info {
+    pub id: DeviceId,
+    pub name: String,
+    pub path: String,
+    pub class: Option<&'static str>,
+    pub driver: Option<&'static str>,
+    pub attrs: Vec<(&'static str, String)>,
+}
+
+/// Find or create `name` under `parent` (`None` for the top level). Calling
+/// it again with the same name updates the class and driver.
+pub fn add(
+    parent: Option<DeviceId>,
+    name: &str,
+    class: Option<&'static str>,
+    driver: Option<&'static str>,
+) -> DeviceId {
+    let mut tree = TREE.lock();
+    if let Some(id) = tree.child(parent, name) {
+        if let Some(node) = tree.get_mut(id) {
+            node.class = class.or(node.class);
+            node.driver = driver.or(node.driver);
+        }
+        return id;
+    }
+    let id = DeviceId(tree.next_id);
+    tree.next_id += 1;
+    tree.nodes.push(Node {
+        id,
+        parent,
+        name: String::from(name),
+        class,
+        driver,
+        attrs: Vec::new(),
+    });
+    id
+}
+
+/// Add a class device named `<prefix><n>` with the lowest free `n` in the
+/// class, like `eth0`, `eth1`.
+pub fn add_class_device(parent: Option<DeviceId>, prefix: &str, class: &'static str, driver: &'static str) -> DeviceId {
+    let taken: Vec<String> = TREE
+        .lock()
+        .nodes
+        .iter()
+        .filter(|n| n.class == Some(class))
+        .map(|n| n.name.clone())
+        .collect();
+    let index = (0..)
+        .find(|i| !taken.iter().any(|name| *name == alloc::format!("{}{}", prefix, i)))
+        .unwrap_or(0);
+    add(
+        parent,
+        alloc::format!("{}{}", prefix, index).as_str(),
+        Some(class),
+        Some(driver),
+    )
+}
+
+fn put_attr(id: DeviceId, key: &'static str, value: Attr) {
+    let mut tree = TREE.lock();
+    let Some(node) = tree.get_mut(id) else {
+        return;
+    };
+    match node.attrs.iter_mut().find(|(k, _)| *k == key) {
+        Some(slot) => slot.1 = value,
+        None => node.attrs.push((key, value)),
+    }
+}
+
+pub fn set_attr(id: DeviceId, key: &'static str, value: &str) {
+    put_attr(id, key, Attr::Text(String::from(value)));
+}
+
+pub fn set_live(id: DeviceId, key: &'static str, read: fn() -> String) {
+    put_attr(id, key, Attr::Live(read));
+}
+
+pub fn set_driver(id: DeviceId, driver: Option<&'static str>) {
+    if let Some(node) = TREE.lock().get_mut(id) {
+        node.driver = driver;
+    }
+}
+
+/// Remove `id` and everything below it.
+pub fn remove(id: DeviceId) {
+    let mut tree = TREE.lock();
+    let mut doomed = alloc::vec![id];
+    let mut index = 0;
+    while index < doomed.len() {
+        let parent = doomed[index];
+        doomed.extend(tree.nodes.iter().filter(|n| n.parent == Some(parent)).map(|n| n.id));
+        index += 1;
+    }
+    tree.nodes.retain(|n| !doomed.contains(&n.id));
+}
+
+pub fn children(parent: Option<DeviceId>) -> Vec<DeviceId> {
+    TREE.lock()
+        .nodes
+        .iter()
+        .filter(|n| n.parent == parent)
+        .map(|n| n.id)
+        .collect()
+}
+
+pub fn parent(id: DeviceId) -> Option<DeviceId> {
+    TREE.lock().get(id).and_then(|n| n.parent)
+}
+
+pub fn path(id: DeviceId) -> String {
+    TREE.lock().path(id)
+}
+
+/// Node at a `/`-separated path from the top level.
+pub fn lookup(path: &str) -> Option<DeviceId> {
+    let tree = TREE.lock();
+    let mut current = None;
+    for part in path.split('/').filter(|p| !p.is_empty()) {
+        current = Some(tree.child(current, part)?);
+    }
+    current
+}
+
+pub fn info(id: DeviceId) -> Option<Info> {
+    let (mut info, live) = {
+        let tree = TREE.lock();
+        let node = tree.get(id)?;
+        let mut attrs = Vec::new();
+        let mut live = Vec::new();
+        for (key, attr) in node.attrs.iter() {
+            match attr {
+                Attr::Text(text) => attrs.push((*key, text.clone())),
+                Attr::Live(read) => live.push((*key, *read)),
+            }
+        }
+        let info = Info {
+            id,
+            name: node.name.clone(),
+            path: tree.path(id),
+            class: node.class,
+            driver: node.driver,
+            attrs,
+        };
+        (info, live)
+    };
+    // Live readers take driver state; never call them with the tree locked.
+    for (key, read) in live {
+        info.attrs.push((key, read()));
+    }
+    Some(info)
+}
+
+pub fn classes() -> Vec<&'static str> {
+    let mut out: Vec<&'static str> = Vec::new();
+    for class in TREE.lock().nodes.iter().filter_map(|n| n.class) {
+        if !out.contains(&class) {
+            out.push(class);
+        }
+    }
+    out.sort_unstable();
+    out
+}
+
+pub fn class_members(class: &str) -> Vec<DeviceId> {
+    TREE.lock()
+        .nodes
+        .iter()
+        .filter(|n| n.class == Some(class))
+        .map(|n| n.id)
+        .collect()
+}
+
+pub fn pci_name(bus: u8, slot: u8, func: u8) -> String {
+    alloc::format!("0000:{:02x}:{:02x}.{}", bus, slot, func)
+}
+
+/// Node of a PCI function, created on first use.
+pub fn pci_function(device: &crate::pci::PciDevice) -> DeviceId {
+    let root = add(None, PCI_ROOT, None, None);
+    add(
+        Some(root),
+        pci_name(device.bus, device.slot, device.func).as_str(),
+        Some("pci"),
+        None,
+    )
+}
+
+/// Make the PCI subtree match `devices`, as enumerated by `pci::scan`.
+pub fn sync_pci(devices: &[crate::pci::PciDeviceInfo]) {
+    let root = add(None, PCI_ROOT, None, None);
+    for info in devices.iter() {
+        let dev = info.device;
+        let id = pci_function(&dev);
+        set_attr(id, "vendor", alloc::format!("0x{:04x}", dev.vendor_id).as_str());
+        set_attr(id, "device", alloc::format!("0x{:04x}", dev.device_id).as_str());
+        set_attr(
+            id,
+            "class",
+            alloc::format!("0x{:02x}{:02x}{:02x}", info.class_code, info.sub_class, info.prog_if).as_str(),
+        );
+        set_attr(id, "revision", alloc::format!("0x{:02x}", info.revision).as_str());
+        set_attr(
+            id,
+            "subsystem_vendor",
+            alloc::format!("0x{:04x}", info.subsys_vendor_id).as_str(),
+        );
+        set_attr(
+            id,
+            "subsystem_device",
+            alloc::format!("0x{:04x}", info.subsys_id).as_str(),
+        );
+        set_driver(id, info.driver);
+    }
+    for id in children(Some(root)) {
+        let name = info(id).map(|i| i.name).unwrap_or_default();
+        let present = devices
+            .iter()
+            .any(|d| pci_name(d.device.bus, d.device.slot, d.device.func) == name);
+        if !present {
+            remove(id);
+        }
+    }
+}
+
+fn tree_lines(parent: Option<DeviceId>, depth: usize, out: &mut Vec<String>) {
+    for id in children(parent) {
+        let Some(info) = info(id) else {
+            continue;
+        };
+        let mut line = alloc::format!("{:width$}{}", "", info.name, width = depth * 2);
+        if let Some(class) = info.class {
+            line.push_str(alloc::format!(" [{}]", class).as_str());
+        }
+        if let Some(driver) = info.driver {
+            line.push_str(alloc::format!(" ({})", driver).as_str());
+        }
+        out.push(line);
+        tree_lines(Some(id), depth + 1, out);
+    }
+}
+
+/// Node named by a full path or, failing that, by its own name.
+fn find(target: &str) -> Option<DeviceId> {
+    let target = target.trim_start_matches("/sys/devices/").trim_matches('/');
+    lookup(target).or_else(|| {
+        let tree = TREE.lock();
+        tree.nodes.iter().find(|n| n.name == target).map(|n| n.id)
+    })
+}
+
+/// Shared implementation of `devices [tree|class [nombre]|show <dispositivo>]`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let words: Vec<&str> = args.split_whitespace().collect();
+    match words.as_slice() {
+        [] | ["tree"] => {
+            tree_lines(None, 0, &mut out);
+            if out.is_empty() {
+                out.push(String::from("devices: arbol vacio."));
+            }
+        }
+        ["class"] => {
+            for class in classes() {
+                out.push(alloc::format!(
+                    "  {:<10} {} dispositivo(s)",
+                    class,
+                    class_members(class).len()
+                ));
+            }
+        }
+        ["class", class] => {
+            for id in class_members(class) {
+                if let Some(info) = info(id) {
+                    out.push(alloc::format!("  {:<10} /sys/devices/{}", info.name, info.path));
+                }
+            }
+            if out.is_empty() {
+                out.push(alloc::format!("devices: la clase '{}' no tiene dispositivos.", class));
+            }
+        }
+        ["show", target] => match find(target).and_then(info) {
+            Some(info) => {
+                out.push(alloc::format!("/sys/devices/{}", info.path));
+                out.push(alloc::format!("  clase:  {}", info.class.unwrap_or("-")));
+                out.push(alloc::format!("  driver: {}", info.driver.unwrap_or("-")));
+                for (key, value) in info.attrs.iter() {
+                    out.push(alloc::format!("  {} = {}", key, value));
+                }
+                for child in children(Some(info.id)) {
+                    if let Some(child) = self::info(child) {
+                        out.push(alloc::format!("  hijo: {}", child.name));
+                    }
+                }
+            }
+            None => out.push(alloc::format!("devices: '{}' no existe.", target)),
+        },
+        _ => out.push(String::from("Uso: devices [tree|class [nombre]|show <dispositivo>]")),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "device";
+
+    fn builds_and_prunes_the_tree() {
+        let top = add(None, "selftest-bus", None, Some("prueba"));
+        crate::selftest::ensure_eq(add(None, "selftest-bus", None, None), top, "idempotente")?;
+        let child = add_class_device(Some(top), "stest", "selftest", "prueba");
+        let second = add_class_device(Some(top), "stest", "selftest", "prueba");
+        crate::selftest::ensure_eq(path(second), String::from("selftest-bus/stest1"), "segundo nombre")?;
+        crate::selftest::ensure_eq(lookup("selftest-bus/stest0"), Some(child), "lookup")?;
+        set_attr(child, "size", "42");
+        set_live(child, "live", || String::from("vivo"));
+        let attrs = info(child).map(|i| i.attrs).unwrap_or_default();
+        crate::selftest::ensure_eq(
+            attrs,
+            alloc::vec![("size", String::from("42")), ("live", String::from("vivo"))],
+            "atributos",
+        )?;
+        crate::selftest::ensure_eq(class_members("selftest").len(), 2, "clase")?;
+        remove(top);
+        crate::selftest::ensure(lookup("selftest-bus").is_none(), "raiz borrada")?;
+        crate::selftest::ensure(class_members("selftest").is_empty(), "hijos borrados")
+    }
+}
@@ -0,0 +1,363 @@
+//! Driver fault domains.
+//!
+//! Driver entry points run through `call(name, f)`: every PCI probe, the PM
+//! hooks, and the entry points a driver wraps itself. Like setjmp, `call`
+//! saves the callee-saved registers, the stack pointer and RFLAGS before it
+//! runs `f`. If a CPU exception (`interrupts::kernel_fault_entry`) or a
+//! panic hits while a domain is open on that CPU, the handler jumps back to
+//! the saved point instead of stopping the machine. `call` then marks the
+//! driver failed, logs the fault, drops its PM hooks, runs the `release`
+//! the driver registered and returns `None`. Once a driver has failed, every
+//! later `call` for it returns `None` without running anything.
+//!
+//! Nothing between the fault and the saved point is unwound. Heap memory
+//! the driver was holding leaks and its locks stay taken; `release` exists
+//! to undo that. A fault taken while the heap lock is held is not
+//! recovered, because nothing could allocate afterwards.
+//!
+//! Only a fault in the domain's own code is the driver's. Interrupt handlers
+//! bracket their work with `irq_enter`/`irq_exit`, and each domain records
+//! the depth it was opened at; a fault or panic at a deeper level belongs to
+//! a handler that interrupted the driver, so it escalates instead of
+//! unwinding that handler (and its EOI) away.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::arch::global_asm;
+use core::fmt::Write;
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, Ordering};
+
+use crate::klog::FixedBuf;
+use crate::per_core::MAX_CORES;
+use crate::spinlock::SpinLock;
+
+/// What a driver undoes after a fault: force its own locks open, forget
+/// the device and leave the hardware quiet.
+pub struct GuardOps {
+    pub name: &'static str,
+    pub release: fn(),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Failure {
+    pub driver: &'static str,
+    pub reason: String,
+    pub at_ms: u64,
+}
+
+/// Saved by `driver_guard_enter`, in the order the asm below uses.
+#[repr(C)]
+#[derive(Default)]
+struct Jump {
+    rbx: u64,
+    rbp: u64,
+    r12: u64,
+    r13: u64,
+    r14: u64,
+    r15: u64,
+    rsp: u64,
+    rip: u64,
+    rflags: u64,
+}
+
+#[derive(Clone, Copy)]
+enum Cause {
+    None,
+    Exception {
+        vector: u64,
+        error: u64,
+        rip: u64,
+        cr2: u64,
+    },
+    Panic(FixedBuf<160>),
+}
+
+/// One open domain; lives in the frame of `call` on the driver's stack.
+struct Domain {
+    jump: Jump,
+    prev: *mut Domain,
+    cause: Cause,
+    /// Interrupt nesting on this CPU when the domain was opened.
+    irq_depth: u32,
+}
+
+static ACTIVE: [AtomicPtr<Domain>; MAX_CORES] = [const { AtomicPtr::new(core::ptr::null_mut()) }; MAX_CORES];
+static IRQ_DEPTH: [AtomicU32; MAX_CORES] = [const { AtomicU32::new(0) }; MAX_CORES];
+static OPS: SpinLock<Vec<&'static GuardOps>> = SpinLock::new(Vec::new());
+static FAILED: SpinLock<Vec<Failure>> = SpinLock::new(Vec::new());
+static ANY_FAILED: AtomicBool = AtomicBool::new(false);
+
+global_asm!(
+    r#"
+// driver_guard_enter(jump: rdi, entry: rsi, arg: rdx) -> 0, or 1 when
+// driver_guard_resume jumps back.
+.global driver_guard_enter
+driver_guard_enter:
+    mov [rdi + 0], rbx
+    mov [rdi + 8], rbp
+    mov [rdi + 16], r12
+    mov [rdi + 24], r13
+    mov [rdi + 32], r14
+    mov [rdi + 40], r15
+    lea rax, [rsp + 8]
+    mov [rdi + 48], rax
+    mov rax, [rsp]
+    mov [rdi + 56], rax
+    pushfq
+    pop rax
+    mov [rdi + 64], rax
+    sub rsp, 8
+    mov rdi, rdx
+    call rsi
+    add rsp, 8
+    xor eax, eax
+    ret
+
+// driver_guard_resume(jump: rdi) -> !: return 1 from the matching enter.
+.global driver_guard_resume
+driver_guard_resume:
+    mov rbx, [rdi + 0]
+    mov rbp, [rdi + 8]
+    mov r12, [rdi + 16]
+    mov r13, [rdi + 24]
+    mov r14, [rdi + 32]
+    mov r15, [rdi + 40]
+    mov rsp, [rdi + 48]
+    push qword ptr [rdi + 64]
+    popfq
+    mov eax, 1
+    jmp qword ptr [rdi + 56]
+"#
+);
+
+unsafe extern "C" {
+    fn driver_guard_enter(jump: *mut Jump, entry: extern "C" fn(*mut u8), arg: *mut u8) -> u64;
+    fn driver_guard_resume(jump: *const Jump) -> !;
+}
+
+extern "C" fn run<F: FnOnce()>(arg: *mut u8) {
+    if let Some(body) = unsafe { (*(arg as *mut Option<F>)).take() } {
+        body();
+    }
+}
+
+fn enter<F: FnOnce()>(jump: *mut Jump, body: &mut Option<F>) -> bool {
+    unsafe { driver_guard_enter(jump, run::<F>, body as *mut Option<F> as *mut u8) != 0 }
+}
+
+fn cpu() -> usize {
+    crate::smp::current_cpu_index().min(MAX_CORES - 1)
+}
+
+fn slot() -> &'static AtomicPtr<Domain> {
+    &ACTIVE[cpu()]
+}
+
+/// Start of an interrupt handler on this CPU.
+pub fn irq_enter() {
+    IRQ_DEPTH[cpu()].fetch_add(1, Ordering::SeqCst);
+}
+
+/// End of the handler `irq_enter` started.
+pub fn irq_exit() {
+    IRQ_DEPTH[cpu()].fetch_sub(1, Ordering::SeqCst);
+}
+
+/// Adds a driver's release hook; registering the same name again replaces it.
+pub fn register(ops: &'static GuardOps) {
+    let mut list = OPS.lock();
+    match list.iter_mut().find(|existing| existing.name == ops.name) {
+        Some(existing) => *existing = ops,
+        None => list.push(ops),
+    }
+}
+
+/// Runs `f` as driver `name`. `None` when the driver faulted now or before.
+#[inline(never)]
+pub fn call<R>(name: &'static str, f: impl FnOnce() -> R) -> Option<R> {
+    if failed(name) {
+        return None;
+    }
+    let mut result = None;
+    let mut body = Some(|| result = Some(f()));
+    let mut domain = Domain {
+        jump: Jump::default(),
+        prev: core::ptr::null_mut(),
+        cause: Cause::None,
+        irq_depth: IRQ_DEPTH[cpu()].load(Ordering::SeqCst),
+    };
+    let slot = slot();
+    let domain_ptr: *mut Domain = &mut domain;
+    domain.prev = slot.swap(domain_ptr, Ordering::SeqCst);
+    let faulted = enter(unsafe { core::ptr::addr_of_mut!((*domain_ptr).jump) }, &mut body);
+    slot.store(domain.prev, Ordering::SeqCst);
+    drop(body);
+    if faulted {
+        // Written by the fault path behind the compiler's back.
+        let cause = unsafe { core::ptr::read_volatile(core::ptr::addr_of!(domain.cause)) };
+        fail(name, cause);
+        return None;
+    }
+    result
+}
+
+pub fn failed(name: &str) -> bool {
+    ANY_FAILED.load(Ordering::Relaxed) && FAILED.lock().iter().any(|failure| failure.driver == name)
+}
+
+pub fn failure(name: &str) -> Option<Failure> {
+    FAILED.lock().iter().find(|failure| failure.driver == name).cloned()
+}
+
+pub fn failures() -> Vec<Failure> {
+    FAILED.lock().clone()
+}
+
+fn exception_name(vector: u64) -> &'static str {
+    match vector {
+        0 => "#DE",
+        6 => "#UD",
+        8 => "#DF",
+        12 => "#SS",
+        13 => "#GP",
+        14 => "#PF",
+        17 => "#AC",
+        19 => "#XM",
+        _ => "excepcion",
+    }
+}
+
+fn describe(cause: &Cause) -> String {
+    match cause {
+        Cause::None => String::from("fallo sin causa registrada"),
+        Cause::Exception {
+            vector,
+            error,
+            rip,
+            cr2,
+        } => alloc::format!(
+            "{} ({}) error {:#x} en rip {:#x}, cr2 {:#x}",
+            exception_name(*vector),
+            vector,
+            error,
+            rip,
+            cr2
+        ),
+        Cause::Panic(text) => alloc::format!("panic: {}", text.as_str()),
+    }
+}
+
+fn fail(name: &'static str, cause: Cause) {
+    let reason = describe(&cause);
+    FAILED.lock().push(Failure {
+        driver: name,
+        reason: reason.clone(),
+        at_ms: crate::timer::now_ms(),
+    });
+    ANY_FAILED.store(true, Ordering::Relaxed);
+    crate::println(alloc::format!("Driver {}: fallo aislado, desactivado ({}).", name, reason).as_str());
+
+    crate::pm::unregister(name);
+    let release = OPS.lock().iter().find(|ops| ops.name == name).map(|ops| ops.release);
+    if let Some(release) = release {
+        release();
+    }
+    crate::gui::notifications::post(
+        "drivers",
+        "Driver desactivado",
+        alloc::format!(
+            "{} fallo y se ha desactivado; el resto del sistema sigue ({}).",
+            name,
+            reason
+        )
+        .as_str(),
+        crate::gui::notifications::Urgency::Error,
+    );
+}
+
+/// The open domain on this CPU, if the fault can be recovered at all: it
+/// must come from the domain's own code, not from an interrupt handler
+/// that ran on top of it.
+fn recoverable_domain() -> Option<*mut Domain> {
+    let domain = slot().load(Ordering::SeqCst);
+    if domain.is_null() || crate::allocator::heap_locked() {
+        return None;
+    }
+    let depth = IRQ_DEPTH[cpu()].load(Ordering::SeqCst);
+    if unsafe { (*domain).irq_depth } != depth {
+        return None;
+    }
+    Some(domain)
+}
+
+/// From `interrupts::kernel_fault_entry`: returns when the fault is not a
+/// driver's to take.
+pub fn recover_exception(vector: u64, error: u64, rip: u64, cr2: u64) {
+    let Some(domain) = recoverable_domain() else {
+        return;
+    };
+    unsafe {
+        (*domain).cause = Cause::Exception {
+            vector,
+            error,
+            rip,
+            cr2,
+        };
+        driver_guard_resume(core::ptr::addr_of!((*domain).jump));
+    }
+}
+
+/// From the panic handler; same contract as `recover_exception`. Must not
+/// allocate: the panic may come from inside the allocator.
+pub fn recover_panic(info: &PanicInfo) {
+    let Some(domain) = recoverable_domain() else {
+        return;
+    };
+    let mut text = FixedBuf::<160>::new();
+    let _ = write!(text, "{}", info.message());
+    if let Some(location) = info.location() {
+        let _ = write!(text, " ({}:{})", location.file(), location.line());
+    }
+    unsafe {
+        (*domain).cause = Cause::Panic(text);
+        driver_guard_resume(core::ptr::addr_of!((*domain).jump));
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "driver_guard";
+
+    fn panic_and_fault_disable_only_the_driver() {
+        static RELEASED: AtomicBool = AtomicBool::new(false);
+        static OPS_A: GuardOps = GuardOps {
+            name: "selftest_a",
+            release: || RELEASED.store(true, Ordering::SeqCst),
+        };
+        register(&OPS_A);
+        let panicked = call("selftest_a", || -> u32 { panic!("fallo provocado") });
+        // Non-canonical address: #GP, whatever the page tables say.
+        let faulted = call("selftest_b", || unsafe { core::ptr::read_volatile(0x8000_0000_0000_0000 as *const u64) });
+        let reason_a = failure("selftest_a").map(|f| f.reason).unwrap_or_default();
+        let reason_b = failure("selftest_b").map(|f| f.reason).unwrap_or_default();
+        let again = call("selftest_a", || 1u32);
+        let other = call("selftest_c", || call("selftest_d", || 7u32));
+        let in_irq = call("selftest_e", || {
+            irq_enter();
+            let escalates = recoverable_domain().is_none();
+            irq_exit();
+            escalates && recoverable_domain().is_some()
+        });
+        FAILED.lock().retain(|f| !f.driver.starts_with("selftest_"));
+        OPS.lock().retain(|ops| ops.name != "selftest_a");
+
+        crate::selftest::ensure_eq(panicked, None, "panic aislado")?;
+        crate::selftest::ensure(reason_a.contains("fallo provocado"), "motivo del panic")?;
+        crate::selftest::ensure(RELEASED.load(Ordering::SeqCst), "release ejecutado")?;
+        crate::selftest::ensure_eq(faulted, None, "excepcion aislada")?;
+        crate::selftest::ensure(reason_b.starts_with("#GP"), "motivo de la excepcion")?;
+        crate::selftest::ensure_eq(again, None, "driver fallido no vuelve a correr")?;
+        crate::selftest::ensure_eq(other, Some(Some(7)), "dominios anidados sanos")?;
+        crate::selftest::ensure_eq(in_irq, Some(true), "fallo de interrupcion no se atribuye al driver")
+    }
+}
@@ -0,0 +1,526 @@
+//! Async executor for kernel tasks.
+//!
+//! Driver and network code that used to be a hand-written state machine
+//! advanced from a poll loop can be an `async` block instead: `spawn` it and
+//! the main loops (shell, desktop, runtime) run it through `run_ready`, right
+//! after the timer wheel. Tasks live on the kernel thread, so their futures
+//! need not be `Send`. A task is polled again only after its waker fires.
+//! Wakers just set a bit, so a driver may wake them from an IRQ handler.
+//!
+//! Leaf futures here: `sleep_ms` (timer wheel), `yield_now`, and
+//! [`WaitQueue`] for drivers that signal completion. `net::worker::fetch`
+//! gives HTTP requests as futures, and `virtio::block::read_async` (with
+//! `write_async` and `flush_async`) block requests. A frame loop that wants a task's result
+//! spawns it with `spawn_joinable` and checks the [`JoinHandle`] each frame.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use crate::spinlock::SpinLock;
+use crate::sync::KernelCell;
+use crate::timer::{self, TimerId};
+
+/// Tasks alive at once; one bit each in [`WOKEN`].
+pub const MAX_TASKS: usize = 64;
+/// Waker data for the future driven by `block_on`.
+const BLOCK_ON_WAKER: usize = usize::MAX;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaskId {
+    index: u32,
+    generation: u32,
+}
+
+struct Task {
+    name: &'static str,
+    generation: u32,
+    future: Pin<Box<dyn Future<Output = ()>>>,
+    polls: u64,
+}
+
+enum Slot {
+    Free {
+        generation: u32,
+    },
+    /// Taken out by `run_ready` while it is being polled.
+    Running {
+        generation: u32,
+        name: &'static str,
+    },
+    Parked(Task),
+}
+
+impl Slot {
+    fn generation(&self) -> u32 {
+        match self {
+            Slot::Free { generation } | Slot::Running { generation, .. } => *generation,
+            Slot::Parked(task) => task.generation,
+        }
+    }
+}
+
+struct Stats {
+    spawned: u64,
+    completed: u64,
+    polls: u64,
+}
+
+static TASKS: KernelCell<Vec<Slot>> = KernelCell::new(Vec::new());
+static STATS: KernelCell<Stats> = KernelCell::new(Stats {
+    spawned: 0,
+    completed: 0,
+    polls: 0,
+});
+/// One bit per task slot whose waker fired since it was last polled.
+static WOKEN: AtomicU64 = AtomicU64::new(0);
+static BLOCK_ON_WOKEN: AtomicBool = AtomicBool::new(false);
+static RUNNING: AtomicBool = AtomicBool::new(false);
+/// Outputs of `spawn_joinable` tasks, until their handle takes them.
+static RESULTS: KernelCell<Vec<(u64, Box<dyn Any>)>> = KernelCell::new(Vec::new());
+static NEXT_RESULT: AtomicU64 = AtomicU64::new(1);
+
+fn wake_data(data: usize) {
+    if data == BLOCK_ON_WAKER {
+        BLOCK_ON_WOKEN.store(true, Ordering::Release);
+    } else {
+        WOKEN.fetch_or(1 << data, Ordering::AcqRel);
+    }
+}
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(
+    |data| RawWaker::new(data, &VTABLE),
+    |data| wake_data(data as usize),
+    |data| wake_data(data as usize),
+    |_| {},
+);
+
+fn waker_for(data: usize) -> Waker {
+    // SAFETY: the vtable only reads `data` as an integer and owns nothing.
+    unsafe { Waker::from_raw(RawWaker::new(data as *const (), &VTABLE)) }
+}
+
+/// Queue `future` as a task; it is first polled by the next `run_ready`.
+/// `None` when all `MAX_TASKS` slots are taken.
+pub fn spawn(name: &'static str, future: impl Future<Output = ()> + 'static) -> Option<TaskId> {
+    let tasks = unsafe { TASKS.get_mut() };
+    let index = match tasks.iter().position(|slot| matches!(slot, Slot::Free { .. })) {
+        Some(index) => index,
+        None if tasks.len() < MAX_TASKS => {
+            tasks.push(Slot::Free { generation: 0 });
+            tasks.len() - 1
+        }
+        None => return None,
+    };
+    let generation = tasks[index].generation().wrapping_add(1);
+    tasks[index] = Slot::Parked(Task {
+        name,
+        generation,
+        future: Box::pin(future),
+        polls: 0,
+    });
+    unsafe {
+        STATS.get_mut().spawned += 1;
+    }
+    wake_data(index);
+    Some(TaskId {
+        index: index as u32,
+        generation,
+    })
+}
+
+/// Handle to a task started with `spawn_joinable`. Dropping it cancels the
+/// task, or discards its output if it already finished.
+pub struct JoinHandle<T> {
+    task: TaskId,
+    key: u64,
+    _output: PhantomData<T>,
+}
+
+/// Like `spawn`, keeping the output of `future` for [`JoinHandle::try_take`].
+pub fn spawn_joinable<T: 'static>(
+    name: &'static str,
+    future: impl Future<Output = T> + 'static,
+) -> Option<JoinHandle<T>> {
+    let key = NEXT_RESULT.fetch_add(1, Ordering::Relaxed);
+    let task = spawn(name, async move {
+        let output: Box<dyn Any> = Box::new(future.await);
+        unsafe { RESULTS.get_mut() }.push((key, output));
+    })?;
+    Some(JoinHandle {
+        task,
+        key,
+        _output: PhantomData,
+    })
+}
+
+impl<T: 'static> JoinHandle<T> {
+    /// The output once the task has finished; `None` while it still runs.
+    pub fn try_take(&mut self) -> Option<T> {
+        let results = unsafe { RESULTS.get_mut() };
+        let index = results.iter().position(|(key, _)| *key == self.key)?;
+        let (_, output) = results.swap_remove(index);
+        output.downcast::<T>().ok().map(|output| *output)
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        if !cancel(self.task) {
+            let results = unsafe { RESULTS.get_mut() };
+            results.retain(|(key, _)| *key != self.key);
+        }
+    }
+}
+
+/// Drop a task without running it to completion. False when it already
+/// finished, or when it is the task currently being polled.
+pub fn cancel(id: TaskId) -> bool {
+    let task = {
+        let tasks = unsafe { TASKS.get_mut() };
+        let Some(slot) = tasks.get_mut(id.index as usize) else {
+            return false;
+        };
+        if !matches!(slot, Slot::Parked(_)) || slot.generation() != id.generation {
+            return false;
+        }
+        core::mem::replace(
+            slot,
+            Slot::Free {
+                generation: id.generation,
+            },
+        )
+    };
+    // Dropped outside the task list: the future may spawn or cancel on drop.
+    drop(task);
+    true
+}
+
+/// Poll every task whose waker fired; returns how many were polled. Called
+/// from the main loops after `timer::run_due`.
+pub fn run_ready() -> usize {
+    if RUNNING.swap(true, Ordering::Acquire) {
+        return 0;
+    }
+    let mut woken = WOKEN.swap(0, Ordering::AcqRel);
+    let mut polled = 0usize;
+    while woken != 0 {
+        let index = woken.trailing_zeros() as usize;
+        woken &= woken - 1;
+        // Take the task out so it may spawn or cancel others while polled.
+        let mut task = {
+            let tasks = unsafe { TASKS.get_mut() };
+            let Some(slot) = tasks.get_mut(index) else {
+                continue;
+            };
+            let (generation, name) = match slot {
+                Slot::Parked(task) => (task.generation, task.name),
+                _ => continue,
+            };
+            match core::mem::replace(slot, Slot::Running { generation, name }) {
+                Slot::Parked(task) => task,
+                _ => continue,
+            }
+        };
+        let waker = waker_for(index);
+        let mut cx = Context::from_waker(&waker);
+        task.polls += 1;
+        polled += 1;
+        let done = task.future.as_mut().poll(&mut cx).is_ready();
+        let generation = task.generation;
+        if done {
+            drop(task);
+            unsafe {
+                TASKS.get_mut()[index] = Slot::Free { generation };
+                STATS.get_mut().completed += 1;
+            }
+        } else {
+            unsafe {
+                TASKS.get_mut()[index] = Slot::Parked(task);
+            }
+        }
+    }
+    unsafe {
+        STATS.get_mut().polls += polled as u64;
+    }
+    RUNNING.store(false, Ordering::Release);
+    polled
+}
+
+/// Run `future` to completion on the calling loop, servicing the network,
+/// the timer wheel and the other tasks while it waits. For shell commands;
+/// never from inside a task or a `net::poll` callback.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    block_on_with(future, &mut || {})
+}
+
+/// `block_on` for a caller that has to keep its own loop alive, such as a
+/// browser backend that redraws the desktop: `idle` runs whenever no task
+/// was ready.
+pub fn block_on_with<F: Future>(future: F, idle: &mut impl FnMut()) -> F::Output {
+    assert!(
+        !RUNNING.load(Ordering::Acquire),
+        "block_on called from an executor task"
+    );
+    let mut future = core::pin::pin!(future);
+    let waker = waker_for(BLOCK_ON_WAKER);
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        BLOCK_ON_WOKEN.store(false, Ordering::Release);
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        while !BLOCK_ON_WOKEN.load(Ordering::Acquire) {
+            crate::net::poll();
+            timer::run_due();
+            if run_ready() == 0 {
+                idle();
+                crate::hal::pause();
+            }
+        }
+    }
+}
+
+/// Wakers of futures waiting on one event, such as a driver completion.
+/// `wake_all` may run in an IRQ handler: it neither allocates nor frees.
+pub struct WaitQueue {
+    wakers: SpinLock<Vec<Waker>>,
+}
+
+impl WaitQueue {
+    pub const fn new() -> Self {
+        Self {
+            wakers: SpinLock::new(Vec::new()),
+        }
+    }
+
+    /// Wake `waker` on the next `wake_all`. Call from `poll` before
+    /// returning `Pending`, after checking the condition.
+    pub fn register(&self, waker: &Waker) {
+        let mut wakers = self.wakers.lock();
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+
+    pub fn wake_all(&self) {
+        for waker in self.wakers.lock().drain(..) {
+            waker.wake();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.wakers.lock().is_empty()
+    }
+}
+
+impl Default for WaitQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wakers of pending `Sleep`s, indexed by the argument of their timer.
+static SLEEPERS: KernelCell<Vec<Option<Option<Waker>>>> = KernelCell::new(Vec::new());
+
+fn wake_sleeper(slot: usize) {
+    let sleepers = unsafe { SLEEPERS.get_mut() };
+    if let Some(Some(waker)) = sleepers.get_mut(slot) {
+        if let Some(waker) = waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Future returned by [`sleep_ms`].
+pub struct Sleep {
+    deadline_ms: u64,
+    /// Sleeper slot and the timer that wakes it, once first polled.
+    armed: Option<(usize, TimerId)>,
+}
+
+/// Complete `ms` milliseconds from now, on the timer wheel.
+pub fn sleep_ms(ms: u64) -> Sleep {
+    Sleep {
+        deadline_ms: timer::now_ms().saturating_add(ms),
+        armed: None,
+    }
+}
+
+impl Sleep {
+    fn disarm(&mut self) {
+        if let Some((slot, id)) = self.armed.take() {
+            timer::cancel(id);
+            unsafe {
+                SLEEPERS.get_mut()[slot] = None;
+            }
+        }
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let now = timer::now_ms();
+        if now >= self.deadline_ms {
+            self.disarm();
+            return Poll::Ready(());
+        }
+        let sleepers = unsafe { SLEEPERS.get_mut() };
+        match self.armed {
+            Some((slot, _)) => sleepers[slot] = Some(Some(cx.waker().clone())),
+            None => {
+                let slot = match sleepers.iter().position(Option::is_none) {
+                    Some(slot) => slot,
+                    None => {
+                        sleepers.push(None);
+                        sleepers.len() - 1
+                    }
+                };
+                sleepers[slot] = Some(Some(cx.waker().clone()));
+                let id = timer::after_ms(self.deadline_ms - now, wake_sleeper, slot);
+                self.armed = Some((slot, id));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        self.disarm();
+    }
+}
+
+/// Future returned by [`yield_now`].
+pub struct YieldNow {
+    yielded: bool,
+}
+
+/// Let the other ready tasks run before continuing.
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Live tasks and how many are waiting to be polled.
+pub fn task_counts() -> (usize, usize) {
+    let tasks = unsafe { TASKS.get() };
+    let live = tasks.iter().filter(|slot| !matches!(slot, Slot::Free { .. })).count();
+    (live, WOKEN.load(Ordering::Acquire).count_ones() as usize)
+}
+
+/// `async`: tasks on the executor and its counters.
+pub fn command_lines(_args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let woken = WOKEN.load(Ordering::Acquire);
+    let tasks = unsafe { TASKS.get() };
+    let stats = unsafe { STATS.get() };
+    let (live, ready) = task_counts();
+    out.push(alloc::format!(
+        "Executor: {} tareas ({} listas), {} creadas, {} terminadas, {} polls",
+        live,
+        ready,
+        stats.spawned,
+        stats.completed,
+        stats.polls
+    ));
+    for (index, slot) in tasks.iter().enumerate() {
+        let (name, polls, state) = match slot {
+            Slot::Free { .. } => continue,
+            Slot::Running { name, .. } => (*name, 0, "ejecutando"),
+            Slot::Parked(task) => (
+                task.name,
+                task.polls,
+                if woken & (1 << index) != 0 {
+                    "lista"
+                } else {
+                    "esperando"
+                },
+            ),
+        };
+        out.push(alloc::format!("  [{}] {} {} polls={}", index, name, state, polls));
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "executor";
+
+    fn spawned_task_runs_to_completion() {
+        static STEPS: AtomicU64 = AtomicU64::new(0);
+
+        STEPS.store(0, Ordering::SeqCst);
+        let id = spawn("selftest", async {
+            STEPS.fetch_add(1, Ordering::SeqCst);
+            yield_now().await;
+            STEPS.fetch_add(1, Ordering::SeqCst);
+        })
+        .ok_or_else(|| String::from("sin huecos"))?;
+        run_ready();
+        crate::selftest::ensure_eq(STEPS.load(Ordering::SeqCst), 1, "primer poll")?;
+        run_ready();
+        crate::selftest::ensure_eq(STEPS.load(Ordering::SeqCst), 2, "tras yield")?;
+        crate::selftest::ensure(!cancel(id), "ya terminada")
+    }
+
+    fn sleep_wakes_after_deadline() {
+        static DONE: AtomicBool = AtomicBool::new(false);
+
+        DONE.store(false, Ordering::SeqCst);
+        let start = timer::now_ms();
+        spawn("selftest-sleep", async {
+            sleep_ms(5).await;
+            DONE.store(true, Ordering::SeqCst);
+        })
+        .ok_or_else(|| String::from("sin huecos"))?;
+        let deadline = timer::Deadline::after_ms(200);
+        while !DONE.load(Ordering::SeqCst) && !deadline.expired() {
+            timer::run_due();
+            run_ready();
+        }
+        crate::selftest::ensure(DONE.load(Ordering::SeqCst), "despertada")?;
+        crate::selftest::ensure(timer::now_ms() >= start + 5, "no antes de tiempo")
+    }
+
+    fn join_handle_takes_output() {
+        let mut handle = spawn_joinable("selftest-join", async {
+            yield_now().await;
+            7u32
+        })
+        .ok_or_else(|| String::from("sin huecos"))?;
+        run_ready();
+        crate::selftest::ensure_eq(handle.try_take(), None, "aun en curso")?;
+        run_ready();
+        crate::selftest::ensure_eq(handle.try_take(), Some(7), "resultado")?;
+        crate::selftest::ensure_eq(handle.try_take(), None, "solo una vez")
+    }
+
+    fn cancel_drops_pending_task() {
+        let id = spawn("selftest-cancel", sleep_ms(60_000)).ok_or_else(|| String::from("sin huecos"))?;
+        run_ready();
+        let armed = timer::armed_count();
+        crate::selftest::ensure(cancel(id), "cancelar")?;
+        crate::selftest::ensure_eq(timer::armed_count(), armed - 1, "temporizador retirado")?;
+        crate::selftest::ensure(!cancel(id), "cancelar dos veces")
+    }
+}
@@ -1,7 +1,12 @@
+use alloc::collections::BTreeMap;
 use alloc::string::String;
 use alloc::vec::Vec;
 use crate::fs::{DirEntry, FileType, FileSystem};
+use crate::fsjournal::Sector;
+use crate::spinlock::SpinLock;
 use crate::virtio::block;
+use crate::sync::KernelCell;
+use crate::fswatch::Change;
 use uefi::boot::{self, OpenProtocolAttributes, OpenProtocolParams};
 use uefi::proto::media::block::BlockIO;
 use uefi::proto::loaded_image::LoadedImage;
@@ -13,6 +18,12 @@ const FAT32_COPY_IO_MIN_BYTES: usize = 64 * 1024;
 const FAT32_COPY_IO_REMOVABLE_BYTES: usize = 256 * 1024;
 const FAT32_COPY_IO_MAX_BYTES: usize = 1024 * 1024;
 const FAT32_EOC: u32 = 0x0FFF_FFFF;
+const FSINFO_LEAD_SIG: u32 = 0x4161_5252;
+const FSINFO_STRUCT_SIG: u32 = 0x6141_7272;
+const FSINFO_TRAIL_SIG: u32 = 0xAA55_0000;
+const FSINFO_UNKNOWN: u32 = 0xFFFF_FFFF;
+/// FAT sectors read at once when the free clusters are counted.
+const FREE_COUNT_SPAN_SECTORS: usize = 64;
 const FAT32_DIR_ATTR_LFN: u8 = 0x0F;
 
 // FAT32 Boot Sector Structure
@@ -166,6 +177,55 @@ struct ExFatEntrySetInfo {
     name: String,
 }
 
+/// Metadata sectors of the update in progress and where the volume's
+/// journal lives (`crate::fsjournal`).
+struct JournalTx {
+    /// Nesting of public mutators; staging covers the outermost one.
+    depth: u32,
+    staged: Option<BTreeMap<u64, Sector>>,
+    /// LBAs of the journal file; empty when the volume has none.
+    sectors: Vec<u64>,
+    seq: u64,
+    /// Creating the journal was already tried on this mount.
+    tried: bool,
+}
+
+impl JournalTx {
+    const fn new() -> Self {
+        Self {
+            depth: 0,
+            staged: None,
+            sectors: Vec::new(),
+            seq: 0,
+            tried: false,
+        }
+    }
+}
+
+/// The FAT32 FSInfo sector: free-cluster count and allocation hint. Read on
+/// the first FAT update after a mount, kept current by `write_fat_entry` and
+/// written back when a metadata update commits and on unmount.
+#[derive(Clone, Copy)]
+struct FsInfo {
+    /// Sector within the partition, from the boot sector; 0 when absent.
+    sector: u16,
+    loaded: bool,
+    /// `None` while unknown (0xFFFFFFFF on disk).
+    free: Option<u32>,
+    dirty: bool,
+}
+
+impl FsInfo {
+    const fn new(sector: u16) -> Self {
+        Self {
+            sector,
+            loaded: false,
+            free: None,
+            dirty: false,
+        }
+    }
+}
+
 pub struct Fat32 {
     pub bytes_per_sector: u16,
     pub sectors_per_cluster: u8,
@@ -184,27 +244,16 @@ pub struct Fat32 {
     exfat_cluster_count: u32,
     exfat_stream_cache: Option<Vec<ExFatStreamInfo>>,
     pub boot_partition_lba: Option<u64>,
+    journal: SpinLock<JournalTx>,
+    /// Data clusters of the mounted FAT32 volume, from the boot sector.
+    fat32_cluster_count: u32,
+    fsinfo: FsInfo,
 }
 
-pub static mut GLOBAL_FAT: Fat32 = Fat32 {
-    bytes_per_sector: 0,
-    sectors_per_cluster: 0,
-    reserved_sectors: 0,
-    fats: 0,
-    sectors_per_fat: 0,
-    root_cluster: 0,
-    partition_start: 0,
-    fat_start: 0,
-    data_start: 0,
-    volume_label: [0; 11],
-    init_status: InitStatus::Uninitialized,
-    uefi_block_handle: None,
-    next_free_cluster_hint: 2,
-    mounted_fs: DetectedFsKind::Unknown,
-    exfat_cluster_count: 0,
-    exfat_stream_cache: None,
-    boot_partition_lba: None,
-};
+/// The mounted boot volume. Callers borrow it for whole operations and call
+/// back into code that borrows it again, so it lives in a `KernelCell`
+/// rather than behind a lock.
+pub static GLOBAL_FAT: KernelCell<Fat32> = KernelCell::new(Fat32::new());
 
 #[derive(Clone, Copy)]
 struct ProbeResult {
@@ -218,6 +267,8 @@ struct ProbeResult {
     fat_start: u64,
     data_start: u64,
     volume_label: [u8; 11],
+    cluster_count: u32,
+    fs_info_sector: u16,
 }
 
 #[derive(Clone, Copy)]
@@ -293,7 +344,7 @@ struct AlignedBlock([u8; MAX_UEFI_BLOCK_SIZE]);
 #[repr(align(4096))]
 struct CopyIoBuffer([u8; FAT32_COPY_IO_MAX_BYTES]);
 
-static mut FAT32_COPY_IO_BUFFER: CopyIoBuffer = CopyIoBuffer([0u8; FAT32_COPY_IO_MAX_BYTES]);
+static FAT32_COPY_IO_BUFFER: KernelCell<CopyIoBuffer> = KernelCell::new(CopyIoBuffer([0u8; FAT32_COPY_IO_MAX_BYTES]));
 
 impl Fat32 {
     pub const fn new() -> Self {
@@ -315,10 +366,14 @@ impl Fat32 {
             exfat_cluster_count: 0,
             exfat_stream_cache: None,
             boot_partition_lba: None,
+            journal: SpinLock::new(JournalTx::new()),
+            fat32_cluster_count: 0,
+            fsinfo: FsInfo::new(0),
         }
     }
 
     pub fn unmount(&mut self) {
+        self.store_fsinfo();
         self.bytes_per_sector = 0;
         self.sectors_per_cluster = 0;
         self.reserved_sectors = 0;
@@ -335,6 +390,9 @@ impl Fat32 {
         self.mounted_fs = DetectedFsKind::Unknown;
         self.exfat_cluster_count = 0;
         self.exfat_stream_cache = None;
+        *self.journal.lock() = JournalTx::new();
+        self.fat32_cluster_count = 0;
+        self.fsinfo = FsInfo::new(0);
         // Do NOT reset boot_partition_lba here so it persists across remounts
     }
 
@@ -347,7 +405,7 @@ impl Fat32 {
 
     fn read_sector_virtio_or_nvme(&self, lba: u64, buffer: &mut [u8]) -> bool {
         // Try VirtIO first
-        if block::read(lba, buffer) {
+        if block::read_blocks(lba, &mut buffer[..SECTOR_SIZE]) {
             return true;
         }
         // Fallback to NVMe
@@ -363,10 +421,19 @@ impl Fat32 {
         }
 
         // Write support exists on VirtIO. NVMe write path is not implemented yet.
-        block::write(lba, &buffer[0..SECTOR_SIZE])
+        block::write_blocks(lba, &buffer[0..SECTOR_SIZE])
     }
 
-    fn read_sector_from_uefi_handle(handle: Handle, lba: u64, buffer: &mut [u8]) -> bool {
+    /// One sector through a UEFI block handle, decrypted when `crypt` maps it.
+    pub(crate) fn read_sector_from_uefi_handle(handle: Handle, lba: u64, buffer: &mut [u8]) -> bool {
+        if !Self::read_sector_from_uefi_handle_raw(handle, lba, buffer) {
+            return false;
+        }
+        crate::crypt::decrypt(handle, lba, &mut buffer[..SECTOR_SIZE]);
+        true
+    }
+
+    fn read_sector_from_uefi_handle_raw(handle: Handle, lba: u64, buffer: &mut [u8]) -> bool {
         if buffer.len() < SECTOR_SIZE {
             return false;
         }
@@ -430,7 +497,18 @@ impl Fat32 {
         true
     }
 
-    fn write_sector_from_uefi_handle(handle: Handle, lba: u64, buffer: &[u8]) -> bool {
+    /// One sector through a UEFI block handle, encrypted when `crypt` maps it.
+    pub(crate) fn write_sector_from_uefi_handle(handle: Handle, lba: u64, buffer: &[u8]) -> bool {
+        if buffer.len() < SECTOR_SIZE {
+            return false;
+        }
+        match crate::crypt::encrypt(handle, lba, &buffer[..SECTOR_SIZE]) {
+            Some(sealed) => Self::write_sector_from_uefi_handle_raw(handle, lba, &sealed),
+            None => Self::write_sector_from_uefi_handle_raw(handle, lba, buffer),
+        }
+    }
+
+    fn write_sector_from_uefi_handle_raw(handle: Handle, lba: u64, buffer: &[u8]) -> bool {
         if buffer.len() < SECTOR_SIZE {
             return false;
         }
@@ -500,8 +578,13 @@ impl Fat32 {
             .is_ok()
     }
 
-    // Read 512-byte logical sectors from the active storage source.
+    // Read 512-byte logical sectors from the active storage source, as
+    // staged by the metadata update in progress if it touched them.
     fn read_sector(&self, lba: u64, buffer: &mut [u8]) -> bool {
+        if let Some(staged) = self.journal.lock().staged.as_ref().and_then(|s| s.get(&lba)) {
+            buffer[..SECTOR_SIZE].copy_from_slice(staged);
+            return true;
+        }
         if let Some(handle) = self.uefi_block_handle {
             if Self::read_sector_from_uefi_handle(handle, lba, buffer) {
                 return true;
@@ -511,8 +594,33 @@ impl Fat32 {
         self.read_sector_virtio_or_nvme(lba, buffer)
     }
 
-    // Write one 512-byte logical sector to the active storage source.
+    // Write one metadata sector (FAT, directory). During an update it is
+    // staged for the journal instead; see `begin_tx`.
     fn write_sector(&self, lba: u64, buffer: &[u8]) -> bool {
+        let full = {
+            let mut journal = self.journal.lock();
+            let capacity = crate::fsjournal::capacity(journal.sectors.len());
+            let Some(staged) = journal.staged.as_mut() else {
+                drop(journal);
+                return self.write_sector_direct(lba, buffer);
+            };
+            let mut sector = [0u8; SECTOR_SIZE];
+            sector.copy_from_slice(&buffer[..SECTOR_SIZE]);
+            // An update too large for the journal goes out in pieces; the
+            // pieces already written stay consistent.
+            let full = !staged.contains_key(&lba) && staged.len() >= capacity.max(1);
+            let flushed = if full { Some(core::mem::take(staged)) } else { None };
+            staged.insert(lba, sector);
+            flushed
+        };
+        if let Some(flushed) = full {
+            self.flush_staged(flushed);
+        }
+        true
+    }
+
+    // Write one 512-byte logical sector to the active storage source.
+    fn write_sector_direct(&self, lba: u64, buffer: &[u8]) -> bool {
         if let Some(handle) = self.uefi_block_handle {
             if Self::write_sector_from_uefi_handle(handle, lba, buffer) {
                 return true;
@@ -527,6 +635,19 @@ impl Fat32 {
         lba: u64,
         sectors: usize,
         buffer: &mut [u8],
+    ) -> bool {
+        if !Self::read_sector_span_from_uefi_handle_raw(handle, lba, sectors, buffer) {
+            return false;
+        }
+        crate::crypt::decrypt(handle, lba, &mut buffer[..sectors * SECTOR_SIZE]);
+        true
+    }
+
+    fn read_sector_span_from_uefi_handle_raw(
+        handle: Handle,
+        lba: u64,
+        sectors: usize,
+        buffer: &mut [u8],
     ) -> bool {
         if sectors == 0 {
             return true;
@@ -634,6 +755,21 @@ impl Fat32 {
         lba: u64,
         sectors: usize,
         buffer: &[u8],
+    ) -> bool {
+        let Some(total_bytes) = sectors.checked_mul(SECTOR_SIZE).filter(|&len| len <= buffer.len()) else {
+            return false;
+        };
+        match crate::crypt::encrypt(handle, lba, &buffer[..total_bytes]) {
+            Some(sealed) => Self::write_sector_span_from_uefi_handle_raw(handle, lba, sectors, &sealed),
+            None => Self::write_sector_span_from_uefi_handle_raw(handle, lba, sectors, buffer),
+        }
+    }
+
+    fn write_sector_span_from_uefi_handle_raw(
+        handle: Handle,
+        lba: u64,
+        sectors: usize,
+        buffer: &[u8],
     ) -> bool {
         if sectors == 0 {
             return true;
@@ -760,9 +896,14 @@ impl Fat32 {
 
         if let Some(handle) = self.uefi_block_handle {
             if Self::read_sector_span_from_uefi_handle(handle, lba, sectors, &mut buffer[..total_bytes]) {
+                self.patch_staged(lba, sectors, buffer);
                 return true;
             }
         }
+        if block::read_blocks(lba, &mut buffer[..total_bytes]) {
+            self.patch_staged(lba, sectors, buffer);
+            return true;
+        }
 
         let mut i = 0usize;
         while i < sectors {
@@ -776,6 +917,16 @@ impl Fat32 {
         true
     }
 
+    // Overlay the staged metadata sectors that fall in a span just read.
+    fn patch_staged(&self, lba: u64, sectors: usize, buffer: &mut [u8]) {
+        if let Some(staged) = self.journal.lock().staged.as_ref() {
+            for (at, sector) in staged.range(lba..lba + sectors as u64) {
+                let off = (*at - lba) as usize * SECTOR_SIZE;
+                buffer[off..off + SECTOR_SIZE].copy_from_slice(sector);
+            }
+        }
+    }
+
     fn write_sector_span(&self, lba: u64, sectors: usize, buffer: &[u8]) -> bool {
         if sectors == 0 {
             return true;
@@ -787,17 +938,27 @@ impl Fat32 {
         if buffer.len() < total_bytes {
             return false;
         }
+        // A staged copy of any of these sectors would overwrite them later.
+        if let Some(staged) = self.journal.lock().staged.as_mut() {
+            for (at, sector) in staged.range_mut(lba..lba + sectors as u64) {
+                let off = (*at - lba) as usize * SECTOR_SIZE;
+                sector.copy_from_slice(&buffer[off..off + SECTOR_SIZE]);
+            }
+        }
 
         if let Some(handle) = self.uefi_block_handle {
             if Self::write_sector_span_from_uefi_handle(handle, lba, sectors, &buffer[..total_bytes]) {
                 return true;
             }
         }
+        if block::write_blocks(lba, &buffer[..total_bytes]) {
+            return true;
+        }
 
         let mut i = 0usize;
         while i < sectors {
             let off = i * SECTOR_SIZE;
-            if !self.write_sector(lba + i as u64, &buffer[off..off + SECTOR_SIZE]) {
+            if !self.write_sector_direct(lba + i as u64, &buffer[off..off + SECTOR_SIZE]) {
                 return false;
             }
             i += 1;
@@ -895,6 +1056,12 @@ impl Fat32 {
 
         let fat_start = partition_start + bpb.reserved_sectors as u64;
         let data_start = fat_start + (bpb.fats as u64 * bpb.sectors_per_fat_32 as u64);
+        let total_sectors = if bpb.total_sectors_16 != 0 {
+            bpb.total_sectors_16 as u64
+        } else {
+            bpb.total_sectors_32 as u64
+        };
+        let data_sectors = (partition_start + total_sectors).saturating_sub(data_start);
 
         Some(ProbeResult {
             bytes_per_sector: bpb.bytes_per_sector,
@@ -907,6 +1074,8 @@ impl Fat32 {
             fat_start,
             data_start,
             volume_label: bpb.label,
+            cluster_count: (data_sectors / bpb.sectors_per_cluster as u64).min(0x0FFF_FFF5) as u32,
+            fs_info_sector: bpb.fs_info,
         })
     }
 
@@ -1225,6 +1394,9 @@ impl Fat32 {
         self.mounted_fs = DetectedFsKind::Fat32;
         self.exfat_cluster_count = 0;
         self.exfat_stream_cache = None;
+        *self.journal.lock() = JournalTx::new();
+        self.fat32_cluster_count = found.cluster_count;
+        self.fsinfo = FsInfo::new(found.fs_info_sector);
     }
 
     fn apply_exfat_probe_result(&mut self, found: ExFatProbeResult) {
@@ -1240,6 +1412,8 @@ impl Fat32 {
         self.data_start = found.data_start;
         self.next_free_cluster_hint = 2;
         self.mounted_fs = DetectedFsKind::ExFat;
+        self.fat32_cluster_count = 0;
+        self.fsinfo = FsInfo::new(0);
         self.exfat_cluster_count = found.cluster_count;
         self.exfat_stream_cache = Some(Vec::new());
         self.exfat_remember_stream(ExFatStreamInfo {
@@ -1751,6 +1925,7 @@ impl Fat32 {
         self.apply_probe_result(selected.probe);
         self.uefi_block_handle = Some(selected.handle);
         self.init_status = InitStatus::Success;
+        self.open_journal();
 
         Ok(DetectedVolume {
             index: device_index,
@@ -1777,6 +1952,7 @@ impl Fat32 {
         self.apply_probe_result(selected.probe);
         self.uefi_block_handle = Some(selected.handle);
         self.init_status = InitStatus::Success;
+        self.open_journal();
 
         Ok(DetectedVolume {
             index,
@@ -2147,6 +2323,13 @@ impl Fat32 {
     }
 
     pub fn write_file_range(&mut self, start_cluster: u32, offset: usize, buffer: &[u8]) -> Result<usize, &'static str> {
+        self.begin_tx();
+        let result = self.write_file_range_inner(start_cluster, offset, buffer);
+        self.commit_tx();
+        result
+    }
+
+    fn write_file_range_inner(&mut self, start_cluster: u32, offset: usize, buffer: &[u8]) -> Result<usize, &'static str> {
         if self.mounted_fs == DetectedFsKind::ExFat {
             if start_cluster < 2 || buffer.is_empty() {
                 return Ok(0);
@@ -2218,7 +2401,7 @@ impl Fat32 {
                 let to_write = (SECTOR_SIZE - skip_in_sector).min(buffer.len() - written);
                 if to_write < SECTOR_SIZE { if !self.read_sector(lba, &mut sector) { return Err("Read failed"); } }
                 sector[skip_in_sector..skip_in_sector + to_write].copy_from_slice(&buffer[written..written + to_write]);
-                if !self.write_sector(lba, &sector) { return Err("Write failed"); }
+                if !self.write_sector_direct(lba, &sector) { return Err("Write failed"); }
                 written += to_write;
                 skip_in_sector = 0;
                 sec_in_cluster += 1;
@@ -2299,6 +2482,10 @@ impl Fat32 {
     where
         F: FnMut(usize, usize) -> bool,
     {
+        // Whole-chain reads skip the per-sector decryption.
+        if crate::crypt::is_mapped(handle) {
+            return Err("Encrypted volume");
+        }
         if target == 0 {
             return Ok(0);
         }
@@ -2395,6 +2582,9 @@ impl Fat32 {
         if chain.is_empty() {
             return Ok(Some(0));
         }
+        if crate::crypt::is_mapped(handle) {
+            return Ok(None);
+        }
 
         let params = OpenProtocolParams {
             handle,
@@ -2547,11 +2737,13 @@ impl FileSystem for Fat32 {
 
         if self.try_init_from_boot_device() {
             self.init_status = InitStatus::Success;
+            self.open_journal();
             return true;
         }
 
         if self.try_init_via_uefi_blockio() {
             self.init_status = InitStatus::Success;
+            self.open_journal();
             return true;
         }
 
@@ -2568,6 +2760,7 @@ impl FileSystem for Fat32 {
             }
             self.apply_probe_result(selected);
             self.init_status = InitStatus::Success;
+            self.open_journal();
             return true;
         }
 
@@ -4086,7 +4279,7 @@ impl Fat32 {
         let mut dst_sectors_left = dst_total_sectors;
         let mut src_run_lba = 0u64;
         let mut src_run_left = 0usize;
-        let copy_io = unsafe { &mut FAT32_COPY_IO_BUFFER.0 };
+        let copy_io = unsafe { &mut FAT32_COPY_IO_BUFFER.get_mut().0 };
 
         while dst_sectors_left > 0 {
             let Some((mut dst_lba, mut dst_run_left)) = self.next_contiguous_lba_run(
@@ -4174,6 +4367,7 @@ impl Fat32 {
             }
             return Err("Target exists but is a file");
         }
+        crate::perm::authorize_create(self, parent_cluster)?;
 
         let cluster_size = self.cluster_size_bytes();
         if cluster_size == 0 {
@@ -4198,6 +4392,8 @@ impl Fat32 {
             let _ = self.exfat_free_cluster_list(chain.as_slice());
             return Err(e);
         }
+        self.report_change(parent_cluster, name.as_str(), Change::Created);
+        crate::perm::note_created(self, parent_cluster, name.as_str(), true);
         Ok(first_cluster)
     }
 
@@ -4442,6 +4638,7 @@ impl Fat32 {
     }
 
     fn write_fat_entry(&mut self, cluster: u32, value: u32) -> Result<(), &'static str> {
+        self.load_fsinfo();
         let copies = (self.fats as usize).max(1);
         for fat_idx in 0..copies {
             let (lba, offset) = self
@@ -4462,10 +4659,168 @@ impl Fat32 {
             if !self.write_sector(lba, &sector) {
                 return Err("FAT write error");
             }
+            if fat_idx == 0 {
+                self.track_free(old_raw & 0x0FFF_FFFF, value & 0x0FFF_FFFF);
+            }
         }
         Ok(())
     }
 
+    /// Adjust the FSInfo free count for one FAT entry going from `old` to `new`.
+    fn track_free(&mut self, old: u32, new: u32) {
+        let delta = match (old == 0, new == 0) {
+            (true, false) => -1i64,
+            (false, true) => 1,
+            _ => return,
+        };
+        if let Some(free) = self.fsinfo.free {
+            self.fsinfo.free = Some((free as i64 + delta).clamp(0, self.fat32_cluster_count as i64) as u32);
+        }
+        self.fsinfo.dirty = true;
+    }
+
+    /// One past the highest cluster number the volume can allocate.
+    fn cluster_limit(&self) -> u32 {
+        let fat_entries = ((self.sectors_per_fat as u64 * SECTOR_SIZE as u64) / 4) as u32;
+        if self.fat32_cluster_count == 0 {
+            return fat_entries;
+        }
+        fat_entries.min(self.fat32_cluster_count.saturating_add(2))
+    }
+
+    fn fsinfo_lba(&self) -> Option<u64> {
+        let sector = self.fsinfo.sector;
+        let usable = self.mounted_fs == DetectedFsKind::Fat32 && sector != 0 && sector < self.reserved_sectors;
+        usable.then(|| self.partition_start + sector as u64)
+    }
+
+    /// Free count and next-free hint of an FSInfo sector; `None` when its
+    /// signatures are wrong. Either value may be unknown.
+    fn parse_fsinfo(sector: &[u8]) -> Option<(Option<u32>, Option<u32>)> {
+        let word = |off: usize| u32::from_le_bytes([sector[off], sector[off + 1], sector[off + 2], sector[off + 3]]);
+        if sector.len() < SECTOR_SIZE
+            || word(0) != FSINFO_LEAD_SIG
+            || word(484) != FSINFO_STRUCT_SIG
+            || word(508) != FSINFO_TRAIL_SIG
+        {
+            return None;
+        }
+        let known = |value: u32| (value != FSINFO_UNKNOWN).then_some(value);
+        Some((known(word(488)), known(word(492))))
+    }
+
+    fn encode_fsinfo(sector: &mut [u8], free: Option<u32>, next_free: u32) {
+        sector[488..492].copy_from_slice(&free.unwrap_or(FSINFO_UNKNOWN).to_le_bytes());
+        sector[492..496].copy_from_slice(&next_free.to_le_bytes());
+    }
+
+    fn load_fsinfo(&mut self) {
+        if self.fsinfo.loaded {
+            return;
+        }
+        self.fsinfo.loaded = true;
+        let Some(lba) = self.fsinfo_lba() else {
+            return;
+        };
+        let mut sector = [0u8; SECTOR_SIZE];
+        if !self.read_sector(lba, &mut sector) {
+            return;
+        }
+        let Some((free, next_free)) = Self::parse_fsinfo(&sector) else {
+            return;
+        };
+        // Values from another OS are hints; ignore the impossible ones.
+        self.fsinfo.free = free.filter(|free| *free <= self.fat32_cluster_count);
+        if let Some(next) = next_free.filter(|next| *next >= 2 && *next < self.cluster_limit()) {
+            self.next_free_cluster_hint = next;
+        }
+    }
+
+    /// Write the free count and hint back if they changed. Inside a metadata
+    /// update the sector joins it.
+    fn store_fsinfo(&mut self) {
+        if !self.fsinfo.dirty {
+            return;
+        }
+        self.fsinfo.dirty = false;
+        let Some(lba) = self.fsinfo_lba() else {
+            return;
+        };
+        let mut sector = [0u8; SECTOR_SIZE];
+        if !self.read_sector(lba, &mut sector) || Self::parse_fsinfo(&sector).is_none() {
+            return;
+        }
+        Self::encode_fsinfo(&mut sector, self.fsinfo.free, self.next_free_cluster_hint);
+        let _ = self.write_sector(lba, &sector);
+    }
+
+    /// Free entries in the first FAT, read a span of sectors at a time.
+    fn count_free_clusters(&self) -> Option<u32> {
+        let limit = self.cluster_limit();
+        let mut buffer = alloc::vec![0u8; FREE_COUNT_SPAN_SECTORS * SECTOR_SIZE];
+        let mut free = 0u32;
+        let mut cluster = 0u32;
+        let mut sector = 0u64;
+        while cluster < limit {
+            let count = (self.sectors_per_fat as u64 - sector).min(FREE_COUNT_SPAN_SECTORS as u64) as usize;
+            if count == 0 {
+                break;
+            }
+            let bytes = &mut buffer[..count * SECTOR_SIZE];
+            if !self.read_sector_span(self.fat_start + sector, count, bytes) {
+                return None;
+            }
+            for entry in bytes.chunks_exact(4) {
+                let value = u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]) & 0x0FFF_FFFF;
+                if cluster >= 2 && cluster < limit && value == 0 {
+                    free += 1;
+                }
+                cluster += 1;
+            }
+            sector += count as u64;
+        }
+        Some(free)
+    }
+
+    /// Recount the free clusters from the FAT and record them in FSInfo,
+    /// for a count that is unknown or was left wrong by another system.
+    pub fn recount_free_clusters(&mut self) -> Option<u32> {
+        if self.mounted_fs != DetectedFsKind::Fat32 {
+            return None;
+        }
+        self.load_fsinfo();
+        let free = self.count_free_clusters()?;
+        if self.fsinfo.free != Some(free) {
+            self.fsinfo.free = Some(free);
+            self.fsinfo.dirty = true;
+        }
+        Some(free)
+    }
+
+    /// Free clusters of the mounted FAT32 volume, counted once if FSInfo
+    /// does not say.
+    pub fn free_clusters(&mut self) -> Option<u32> {
+        self.load_fsinfo();
+        match self.fsinfo.free {
+            Some(free) => Some(free),
+            None => {
+                let free = self.recount_free_clusters();
+                self.store_fsinfo();
+                free
+            }
+        }
+    }
+
+    /// Free bytes of the mounted volume, from `free_clusters`.
+    pub fn free_bytes(&mut self) -> Option<u64> {
+        let free = self.free_clusters()?;
+        Some(free as u64 * self.cluster_size_bytes() as u64)
+    }
+
+    pub fn total_clusters(&self) -> u32 {
+        self.fat32_cluster_count
+    }
+
     fn free_cluster_chain(&mut self, start_cluster: u32) -> Result<(), &'static str> {
         let mut cluster = start_cluster;
         let mut guard = 0usize;
@@ -4489,10 +4844,15 @@ impl Fat32 {
     }
 
     fn find_free_cluster(&mut self) -> Result<u32, &'static str> {
-        let total_entries = ((self.sectors_per_fat as u64 * SECTOR_SIZE as u64) / 4) as u32;
+        let total_entries = self.cluster_limit();
         if total_entries <= 2 {
             return Err("Invalid FAT size");
         }
+        // Trust a full FSInfo only after the FAT agrees.
+        self.load_fsinfo();
+        if self.fsinfo.free == Some(0) && self.recount_free_clusters() == Some(0) {
+            return Err("No free clusters");
+        }
 
         let mut start = self.next_free_cluster_hint;
         if start < 2 || start >= total_entries {
@@ -4757,6 +5117,17 @@ impl Fat32 {
         &mut self,
         parent_cluster: u32,
         name: &str,
+    ) -> Result<u32, &'static str> {
+        self.begin_tx();
+        let result = self.ensure_subdirectory_inner(parent_cluster, name);
+        self.commit_tx();
+        result
+    }
+
+    fn ensure_subdirectory_inner(
+        &mut self,
+        parent_cluster: u32,
+        name: &str,
     ) -> Result<u32, &'static str> {
         if self.bytes_per_sector == 0 {
             return Err("Filesystem not initialized");
@@ -4829,6 +5200,7 @@ impl Fat32 {
         }
 
         // Not found, create new
+        crate::perm::authorize_create(self, parent_cluster)?;
         let (slot_ci, slot_sec, slot_idx) = if let Some(free) = free_slot {
             free
         } else {
@@ -4905,9 +5277,404 @@ impl Fat32 {
 
         self.write_sector(lba, &parent_sector);
 
+        self.report_change(parent_cluster, name, Change::Created);
+        crate::perm::note_created(self, parent_cluster, name, true);
         Ok(subdir_cluster)
     }
 
+    /// Whole contents of the file `filename` in `dir_cluster`.
+    pub fn read_file_in_dir(&mut self, dir_cluster: u32, filename: &str) -> Result<Vec<u8>, &'static str> {
+        crate::perm::authorize_read(self, dir_cluster, filename)?;
+        let entries = self.read_dir_entries(dir_cluster)?;
+        let entry = entries
+            .iter()
+            .find(|e| e.valid && e.file_type == FileType::File && e.matches_name(filename))
+            .ok_or("Archivo no encontrado en el volumen FAT.")?;
+        let mut data = alloc::vec![0u8; entry.size as usize];
+        let len = self.read_file_sized(entry.cluster, entry.size as usize, &mut data)?;
+        data.truncate(len);
+        Ok(data)
+    }
+
+    /// Stable id of this volume, for `crate::fswatch` events and the saved
+    /// search index: the same drive gives the same id on every mount.
+    pub fn volume_id(&self) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut feed = |bytes: &[u8]| {
+            for b in bytes {
+                hash = (hash ^ *b as u64).wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        };
+        feed(&self.volume_label);
+        feed(&self.partition_start.to_le_bytes());
+        feed(&self.root_cluster.to_le_bytes());
+        hash.max(1)
+    }
+
+    /// LBAs of this volume's journal file, if it has a whole one.
+    fn journal_sectors(&mut self) -> Option<Vec<u64>> {
+        let root = self.root_cluster;
+        let dir = self
+            .read_dir_entries(root)
+            .ok()?
+            .into_iter()
+            .find(|e| e.valid && e.file_type == FileType::Directory && e.matches_name(crate::fsjournal::DIR))?;
+        let dir = if dir.cluster < 2 { root } else { dir.cluster };
+        let file = self
+            .read_dir_entries(dir)
+            .ok()?
+            .into_iter()
+            .find(|e| e.valid && e.file_type == FileType::File && e.matches_name(crate::fsjournal::FILE))?;
+        if file.cluster < 2 || (file.size as usize) < crate::fsjournal::SECTORS * SECTOR_SIZE {
+            return None;
+        }
+        let per_cluster = (self.sectors_per_cluster as usize).max(1);
+        let clusters = crate::fsjournal::SECTORS.div_ceil(per_cluster);
+        let chain = self.read_cluster_chain(file.cluster, clusters).ok()?;
+        let mut sectors = Vec::with_capacity(crate::fsjournal::SECTORS);
+        for cluster in chain.iter() {
+            let lba = self.cluster_to_lba(*cluster);
+            sectors.extend((0..per_cluster as u64).map(|i| lba + i));
+        }
+        if sectors.len() < crate::fsjournal::SECTORS {
+            return None;
+        }
+        sectors.truncate(crate::fsjournal::SECTORS);
+        Some(sectors)
+    }
+
+    /// Find the journal of a freshly mounted FAT32 volume and finish the
+    /// update it recorded, if one was cut short.
+    fn open_journal(&mut self) {
+        if self.mounted_fs != DetectedFsKind::Fat32 {
+            return;
+        }
+        let Some(sectors) = self.journal_sectors() else {
+            return;
+        };
+        let mut raw = [0u8; SECTOR_SIZE];
+        let header = if self.read_sector(sectors[0], &mut raw) {
+            crate::fsjournal::Header::decode(&raw)
+        } else {
+            None
+        };
+        let mut seq = header.map(|h| h.seq).unwrap_or(0);
+        if let Some(header) = header.filter(|h| h.state == crate::fsjournal::State::Committed) {
+            let count = crate::fsjournal::body_sectors(header.count as usize);
+            let mut body = Vec::with_capacity(count);
+            for lba in sectors.iter().skip(1).take(count) {
+                let mut sector = [0u8; SECTOR_SIZE];
+                if !self.read_sector(*lba, &mut sector) {
+                    break;
+                }
+                body.push(sector);
+            }
+            match crate::fsjournal::decode(&header, &body) {
+                Some(writes) => {
+                    let mut replayed = 0usize;
+                    for (lba, data) in writes.iter() {
+                        // Only FAT and data-area sectors of this partition.
+                        if *lba >= self.fat_start && self.write_sector_direct(*lba, data) {
+                            replayed += 1;
+                        }
+                    }
+                    crate::klog::log(
+                        crate::klog::Level::Warning,
+                        alloc::format!("fsjournal: {} sectores de metadatos reaplicados", replayed).as_str(),
+                    );
+                }
+                None => crate::klog::log(
+                    crate::klog::Level::Warning,
+                    "fsjournal: transaccion incompleta descartada",
+                ),
+            }
+            seq = seq.wrapping_add(1);
+            let _ = self.write_sector_direct(sectors[0], &crate::fsjournal::Header::clean(seq).encode());
+        }
+        let mut journal = self.journal.lock();
+        journal.sectors = sectors;
+        journal.seq = seq;
+        journal.tried = true;
+    }
+
+    /// Make the journal file of a volume that has none; its first write
+    /// still goes out unjournaled.
+    fn create_journal(&mut self) {
+        let _root = crate::perm::act_as(crate::perm::Cred::ROOT);
+        let root = self.root_cluster;
+        let zeros = alloc::vec![0u8; crate::fsjournal::SECTORS * SECTOR_SIZE];
+        let result = self
+            .ensure_subdirectory(root, crate::fsjournal::DIR)
+            .and_then(|dir| self.write_text_file_in_dir(dir, crate::fsjournal::FILE, &zeros));
+        match result {
+            Ok(()) => self.open_journal(),
+            Err(e) => crate::klog::log(
+                crate::klog::Level::Warning,
+                alloc::format!("fsjournal: volumen sin diario: {}", e).as_str(),
+            ),
+        }
+    }
+
+    /// Start a metadata update: FAT and directory sectors are staged until
+    /// the matching `commit_tx`. Nested calls join the outer update.
+    pub(crate) fn begin_tx(&mut self) {
+        let create = {
+            let mut journal = self.journal.lock();
+            journal.depth += 1;
+            let create = journal.depth == 1 && journal.sectors.is_empty() && !journal.tried;
+            journal.tried |= create;
+            create
+        };
+        if create && self.mounted_fs == DetectedFsKind::Fat32 && self.init_status == InitStatus::Success {
+            self.create_journal();
+        }
+        let mut journal = self.journal.lock();
+        if journal.depth == 1 && self.mounted_fs == DetectedFsKind::Fat32 {
+            journal.staged = Some(BTreeMap::new());
+        }
+    }
+
+    /// End a metadata update; the outermost one writes it, with the FSInfo
+    /// sector if the free count moved, through the journal.
+    pub(crate) fn commit_tx(&mut self) {
+        {
+            let mut journal = self.journal.lock();
+            journal.depth = journal.depth.saturating_sub(1);
+            if journal.depth > 0 {
+                return;
+            }
+        }
+        self.store_fsinfo();
+        let staged = self.journal.lock().staged.take();
+        if let Some(staged) = staged {
+            self.flush_staged(staged);
+        }
+    }
+
+    /// Journal `staged`, write it in place and mark the journal clean.
+    fn flush_staged(&self, staged: BTreeMap<u64, Sector>) {
+        if staged.is_empty() {
+            return;
+        }
+        let writes: Vec<(u64, Sector)> = staged.into_iter().collect();
+        let (sectors, seq) = {
+            let mut journal = self.journal.lock();
+            journal.seq = journal.seq.wrapping_add(1);
+            (journal.sectors.clone(), journal.seq)
+        };
+        let mut journaled = false;
+        if !sectors.is_empty() && writes.len() <= crate::fsjournal::capacity(sectors.len()) {
+            let (header, body) = crate::fsjournal::encode(seq, &writes);
+            journaled = body
+                .iter()
+                .zip(sectors.iter().skip(1))
+                .all(|(data, lba)| self.write_sector_direct(*lba, data))
+                && self.write_sector_direct(sectors[0], &header.encode());
+        }
+        let mut failed = 0usize;
+        for (lba, data) in writes.iter() {
+            if !self.write_sector_direct(*lba, data) {
+                failed += 1;
+            }
+        }
+        if failed > 0 {
+            crate::klog::log(
+                crate::klog::Level::Error,
+                alloc::format!("fsjournal: {} sectores de metadatos sin escribir", failed).as_str(),
+            );
+        } else if journaled {
+            let _ = self.write_sector_direct(sectors[0], &crate::fsjournal::Header::clean(seq).encode());
+        }
+    }
+
+    fn report_change(&self, dir_cluster: u32, name: &str, change: Change) {
+        crate::fswatch::emit(self.volume_id(), self.normalized_dir_cluster(dir_cluster), name, change);
+    }
+
+    /// The journal file is written by LBA, so it must stay where it is.
+    fn protect_journal(&mut self, dir_cluster: u32, name: &str) -> Result<(), &'static str> {
+        if !name.is_empty() && !name.eq_ignore_ascii_case(crate::fsjournal::FILE) {
+            return Ok(());
+        }
+        let Some(first) = self.journal.lock().sectors.first().copied() else {
+            return Ok(());
+        };
+        let entries = self.read_dir_entries(dir_cluster).unwrap_or_default();
+        let hit = entries.iter().any(|e| {
+            e.valid
+                && e.file_type == FileType::File
+                && e.cluster >= 2
+                && (name.is_empty() || e.matches_name(name))
+                && self.cluster_to_lba(e.cluster) == first
+        });
+        if hit {
+            Err("Diario del volumen en uso")
+        } else {
+            Ok(())
+        }
+    }
+
+    // Raw access for `crate::chkdsk`. Writes go through the metadata update
+    // in progress like any other FAT or directory sector.
+
+    /// Entries in one FAT copy, clamped to the FAT32 cluster range.
+    pub(crate) fn fat_entry_count(&self) -> u32 {
+        (self.sectors_per_fat as u64 * (SECTOR_SIZE as u64 / 4)).min(0x0FFF_FFF7) as u32
+    }
+
+    pub(crate) fn fat_entry_sector(&self, cluster: u32) -> Option<(u64, usize)> {
+        self.fat_entry_lba_offset(cluster, 0)
+    }
+
+    pub(crate) fn set_fat_entry(&mut self, cluster: u32, value: u32) -> Result<(), &'static str> {
+        self.write_fat_entry(cluster, value)
+    }
+
+    pub(crate) fn cluster_lba(&self, cluster: u32) -> u64 {
+        self.cluster_to_lba(cluster)
+    }
+
+    pub(crate) fn read_metadata_span(&self, lba: u64, sectors: usize, buffer: &mut [u8]) -> bool {
+        self.read_sector_span(lba, sectors, buffer)
+    }
+
+    pub(crate) fn read_metadata_sector(&self, lba: u64, buffer: &mut [u8]) -> bool {
+        self.read_sector(lba, buffer)
+    }
+
+    pub(crate) fn write_metadata_sector(&self, lba: u64, buffer: &[u8]) -> bool {
+        self.write_sector(lba, buffer)
+    }
+
+    // The public mutators below check the acting credential against
+    // `crate::perm`, run the FAT32/exFAT code as one journaled metadata
+    // update and, once it succeeded, report the change to `crate::fswatch`.
+
+    pub fn write_text_file_in_dir_with_progress<F>(
+        &mut self,
+        dir_cluster: u32,
+        filename: &str,
+        content: &[u8],
+        progress: F,
+    ) -> Result<(), &'static str>
+    where
+        F: FnMut(usize, usize) -> bool,
+    {
+        let created = crate::perm::authorize_write(self, dir_cluster, filename)?;
+        self.protect_journal(dir_cluster, filename)?;
+        self.begin_tx();
+        let result = self.write_text_file_in_dir_inner(dir_cluster, filename, content, progress);
+        self.commit_tx();
+        result?;
+        self.report_change(dir_cluster, filename, Change::Written);
+        if created {
+            crate::perm::note_created(self, dir_cluster, filename, false);
+        }
+        Ok(())
+    }
+
+    pub fn copy_file_from_fat_in_dir_with_progress<F>(
+        &mut self,
+        src_fat: &mut Fat32,
+        src_cluster: u32,
+        src_size: usize,
+        dir_cluster: u32,
+        filename: &str,
+        progress: F,
+    ) -> Result<usize, &'static str>
+    where
+        F: FnMut(usize, usize) -> bool,
+    {
+        let created = crate::perm::authorize_write(self, dir_cluster, filename)?;
+        self.protect_journal(dir_cluster, filename)?;
+        self.begin_tx();
+        let result = self.copy_file_from_fat_in_dir_inner(
+            src_fat,
+            src_cluster,
+            src_size,
+            dir_cluster,
+            filename,
+            progress,
+        );
+        self.commit_tx();
+        let copied = result?;
+        self.report_change(dir_cluster, filename, Change::Written);
+        if created {
+            crate::perm::note_created(self, dir_cluster, filename, false);
+        }
+        Ok(copied)
+    }
+
+    pub fn rename_entry_in_dir(
+        &mut self,
+        dir_cluster: u32,
+        from_name: &str,
+        to_name: &str,
+        expect_directory: Option<bool>,
+    ) -> Result<(), &'static str> {
+        crate::perm::authorize_remove(self, dir_cluster, from_name)?;
+        self.protect_journal(dir_cluster, from_name)?;
+        self.begin_tx();
+        let result = self.rename_entry_in_dir_inner(dir_cluster, from_name, to_name, expect_directory);
+        self.commit_tx();
+        result?;
+        let dir = self.normalized_dir_cluster(dir_cluster);
+        crate::fswatch::emit_move(self.volume_id(), dir, from_name, dir, to_name);
+        Ok(())
+    }
+
+    pub fn delete_directory_in_dir(&mut self, dir_cluster: u32, dirname: &str) -> Result<(), &'static str> {
+        crate::perm::authorize_remove(self, dir_cluster, dirname)?;
+        self.begin_tx();
+        let result = self.delete_directory_in_dir_inner(dir_cluster, dirname);
+        self.commit_tx();
+        result?;
+        self.report_change(dir_cluster, dirname, Change::Removed);
+        Ok(())
+    }
+
+    pub fn delete_file_in_dir(&mut self, dir_cluster: u32, filename: &str) -> Result<(), &'static str> {
+        crate::perm::authorize_remove(self, dir_cluster, filename)?;
+        self.protect_journal(dir_cluster, filename)?;
+        self.begin_tx();
+        let result = self.delete_file_in_dir_inner(dir_cluster, filename);
+        self.commit_tx();
+        result?;
+        self.report_change(dir_cluster, filename, Change::Removed);
+        Ok(())
+    }
+
+    /// Reported as a single `Removed` with an empty name.
+    pub fn empty_directory(&mut self, dir_cluster: u32) -> Result<(), &'static str> {
+        crate::perm::authorize_empty(self, dir_cluster)?;
+        self.protect_journal(dir_cluster, "")?;
+        self.begin_tx();
+        let result = self.empty_directory_inner(dir_cluster);
+        self.commit_tx();
+        result?;
+        self.report_change(dir_cluster, "", Change::Removed);
+        Ok(())
+    }
+
+    pub fn move_entry(&mut self, src_dir_cluster: u32, dst_dir_cluster: u32, filename: &str) -> Result<(), &'static str> {
+        crate::perm::authorize_remove(self, src_dir_cluster, filename)?;
+        crate::perm::authorize_create(self, dst_dir_cluster)?;
+        self.protect_journal(src_dir_cluster, filename)?;
+        self.begin_tx();
+        let result = self.move_entry_inner(src_dir_cluster, dst_dir_cluster, filename);
+        self.commit_tx();
+        result?;
+        crate::fswatch::emit_move(
+            self.volume_id(),
+            self.normalized_dir_cluster(src_dir_cluster),
+            filename,
+            self.normalized_dir_cluster(dst_dir_cluster),
+            filename,
+        );
+        crate::perm::note_moved(self, dst_dir_cluster, filename);
+        Ok(())
+    }
+
     pub fn write_text_file_in_dir(
         &mut self,
         dir_cluster: u32,
@@ -4922,7 +5689,7 @@ impl Fat32 {
         )
     }
 
-    pub fn write_text_file_in_dir_with_progress<F>(
+    fn write_text_file_in_dir_inner<F>(
         &mut self,
         dir_cluster: u32,
         filename: &str,
@@ -5183,7 +5950,7 @@ impl Fat32 {
                         }
 
                         let lba = self.cluster_to_lba(*cluster) + sec as u64;
-                        if !self.write_sector(lba, &sector) {
+                        if !self.write_sector_direct(lba, &sector) {
                             return Err("Data write failed");
                         }
                         let visible_written = written.min(total_len);
@@ -5208,7 +5975,7 @@ impl Fat32 {
         Ok(())
     }
 
-    pub fn copy_file_from_fat_in_dir_with_progress<F>(
+    fn copy_file_from_fat_in_dir_inner<F>(
         &mut self,
         src_fat: &mut Fat32,
         src_cluster: u32,
@@ -5454,7 +6221,7 @@ impl Fat32 {
         let mut src_run_lba = 0u64;
         let mut src_run_left = 0usize;
 
-        let copy_io = unsafe { &mut FAT32_COPY_IO_BUFFER.0 };
+        let copy_io = unsafe { &mut FAT32_COPY_IO_BUFFER.get_mut().0 };
 
         while dst_sectors_left > 0 {
             let Some((mut dst_lba, mut dst_run_left)) = self.next_contiguous_lba_run(
@@ -5558,7 +6325,7 @@ impl Fat32 {
         Ok(true)
     }
 
-    pub fn rename_entry_in_dir(
+    fn rename_entry_in_dir_inner(
         &mut self,
         dir_cluster: u32,
         from_name: &str,
@@ -5692,7 +6459,7 @@ impl Fat32 {
         Ok(())
     }
 
-    pub fn delete_directory_in_dir(
+    fn delete_directory_in_dir_inner(
         &mut self,
         dir_cluster: u32,
         dirname: &str,
@@ -5773,7 +6540,7 @@ impl Fat32 {
         Err("Directory not found")
     }
 
-    pub fn delete_file_in_dir(&mut self, dir_cluster: u32, filename: &str) -> Result<(), &'static str> {
+    fn delete_file_in_dir_inner(&mut self, dir_cluster: u32, filename: &str) -> Result<(), &'static str> {
         if self.bytes_per_sector == 0 {
             return Err("Filesystem not initialized");
         }
@@ -5840,7 +6607,7 @@ impl Fat32 {
         Err("File not found")
     }
 
-    pub fn empty_directory(&mut self, dir_cluster: u32) -> Result<(), &'static str> {
+    fn empty_directory_inner(&mut self, dir_cluster: u32) -> Result<(), &'static str> {
         if self.bytes_per_sector == 0 {
             return Err("Filesystem not initialized");
         }
@@ -5974,7 +6741,7 @@ impl Fat32 {
         Ok((start_cluster, target_cluster))
     }
 
-    pub fn move_entry(&mut self, src_dir_cluster: u32, dst_dir_cluster: u32, filename: &str) -> Result<(), &'static str> {
+    fn move_entry_inner(&mut self, src_dir_cluster: u32, dst_dir_cluster: u32, filename: &str) -> Result<(), &'static str> {
         if self.mounted_fs == DetectedFsKind::ExFat {
             return self.exfat_move_entry(src_dir_cluster, dst_dir_cluster, filename);
         }
@@ -6059,3 +6826,65 @@ impl Fat32 {
         Ok(())
     }
 }
+
+crate::selftest::kernel_tests! {
+    "fat32";
+
+    fn short_name_formatting() {
+        let mut entry = DirEntry::empty();
+        entry.name.copy_from_slice(b"README  TXT");
+        entry.valid = true;
+        crate::selftest::ensure_eq(entry.full_name().as_str(), "README.TXT", "nombre 8.3")?;
+        crate::selftest::ensure(entry.matches_name("readme.txt"), "comparacion sin mayusculas")?;
+        entry.file_type = FileType::Directory;
+        entry.name.copy_from_slice(b"DOCS       ");
+        crate::selftest::ensure_eq(entry.full_name().as_str(), "DOCS", "directorio sin extension")
+    }
+
+    fn long_name_preferred() {
+        let mut entry = DirEntry::empty();
+        entry.name.copy_from_slice(b"LONGFI~1TXT");
+        entry.set_display_name("long file name.txt");
+        crate::selftest::ensure_eq(entry.full_name().as_str(), "long file name.txt", "LFN")?;
+        crate::selftest::ensure(entry.matches_name("LONG FILE NAME.TXT"), "LFN sin mayusculas")
+    }
+
+    fn fsinfo_round_trip_and_tracking() {
+        let mut sector = [0u8; SECTOR_SIZE];
+        sector[0..4].copy_from_slice(&FSINFO_LEAD_SIG.to_le_bytes());
+        sector[484..488].copy_from_slice(&FSINFO_STRUCT_SIG.to_le_bytes());
+        sector[488..492].copy_from_slice(&FSINFO_UNKNOWN.to_le_bytes());
+        sector[492..496].copy_from_slice(&9u32.to_le_bytes());
+        sector[508..512].copy_from_slice(&FSINFO_TRAIL_SIG.to_le_bytes());
+        crate::selftest::ensure_eq(Fat32::parse_fsinfo(&sector), Some((None, Some(9))), "cuenta desconocida")?;
+        Fat32::encode_fsinfo(&mut sector, Some(1000), 42);
+        crate::selftest::ensure_eq(Fat32::parse_fsinfo(&sector), Some((Some(1000), Some(42))), "ida y vuelta")?;
+        sector[510] = 0;
+        crate::selftest::ensure_eq(Fat32::parse_fsinfo(&sector), None, "firma rota")?;
+
+        let mut fat = Fat32::new();
+        fat.fat32_cluster_count = 1000;
+        fat.fsinfo.loaded = true;
+        fat.fsinfo.free = Some(500);
+        fat.track_free(0, FAT32_EOC);
+        fat.track_free(0, 7);
+        fat.track_free(7, FAT32_EOC);
+        fat.track_free(FAT32_EOC, 0);
+        crate::selftest::ensure_eq(fat.fsinfo.free, Some(499), "dos asignados, uno liberado")?;
+        crate::selftest::ensure(fat.fsinfo.dirty, "FSInfo pendiente de escribir")
+    }
+
+    fn mounted_root_readable() {
+        let fat = unsafe { GLOBAL_FAT.get_mut() };
+        if fat.bytes_per_sector == 0 {
+            // Nothing mounted (typical for headless CI): nothing to check.
+            return Ok(());
+        }
+        let root = fat.root_cluster;
+        let entries = fat.read_dir_entries(root).map_err(alloc::string::String::from)?;
+        crate::selftest::ensure(
+            entries.iter().all(|e| !e.valid || !e.full_name().is_empty()),
+            "entrada valida sin nombre",
+        )
+    }
+}
@@ -0,0 +1,396 @@
+//! Framebuffer text console for runtime mode.
+//!
+//! After ExitBootServices there is no firmware console. The runtime shell,
+//! kernel `println` output and `dmesg` all write here, and the runtime
+//! desktop draws it into its terminal panel with the 5x7 `font`.
+//!
+//! Output is kept in a fixed scrollback of `FBCON_SCROLLBACK` lines of
+//! coloured cells, so writing never allocates. PageUp and PageDown move the
+//! view through it; typing returns to the bottom.
+//!
+//! ANSI escapes understood:
+//! - `ESC [ ... m` for colours: 30-37/90-97 foreground, 40-47/100-107
+//!   background, 1 bright, 0/22/39/49 reset.
+//! - `ESC [ 2 J` clears the console.
+//! - `ESC [ K` erases to the end of the line.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::framebuffer;
+use crate::spinlock::SpinLock;
+
+pub const FBCON_SCROLLBACK: usize = 500;
+pub const FBCON_MAX_COLS: usize = 160;
+pub const CELL_W: usize = 6;
+pub const CELL_H: usize = 9;
+/// Colour index meaning "the panel's default".
+const DEFAULT_COLOR: u8 = 0xFF;
+const MAX_PARAMS: usize = 4;
+
+/// The 16 ANSI colours (xterm defaults, slightly softened for the panel).
+const PALETTE: [u32; 16] = [
+    0x1C1C1C, 0xD0504C, 0x5FB86A, 0xD8B04C, 0x4C7FD0, 0xB060C0, 0x4CB0C0, 0xC8D0DC, 0x6A6A6A, 0xFF7A70, 0x8AE68A,
+    0xFFE27A, 0x7AA8FF, 0xE08AF0, 0x7AE0F0, 0xFFFFFF,
+];
+
+#[derive(Clone, Copy)]
+struct Cell {
+    ch: u8,
+    fg: u8,
+    bg: u8,
+}
+
+impl Cell {
+    const BLANK: Self = Self {
+        ch: b' ',
+        fg: DEFAULT_COLOR,
+        bg: DEFAULT_COLOR,
+    };
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Parse {
+    Text,
+    Escape,
+    Csi,
+}
+
+struct Console<const LINES: usize> {
+    lines: [[Cell; FBCON_MAX_COLS]; LINES],
+    lens: [u16; LINES],
+    /// Slot of the line being written.
+    head: usize,
+    /// Lines in use, including the one being written.
+    count: usize,
+    col: usize,
+    /// Wrap width; follows the panel the console was last drawn in.
+    columns: usize,
+    /// Rows shown by the last `render`, the PageUp/PageDown step.
+    rows: usize,
+    /// Lines scrolled back from the bottom.
+    view_offset: usize,
+    fg: u8,
+    bg: u8,
+    bright: bool,
+    parse: Parse,
+    params: [u16; MAX_PARAMS],
+    nparams: usize,
+}
+
+impl<const LINES: usize> Console<LINES> {
+    const fn new() -> Self {
+        Self {
+            lines: [[Cell::BLANK; FBCON_MAX_COLS]; LINES],
+            lens: [0; LINES],
+            head: 0,
+            count: 1,
+            col: 0,
+            columns: 72,
+            rows: 18,
+            view_offset: 0,
+            fg: DEFAULT_COLOR,
+            bg: DEFAULT_COLOR,
+            bright: false,
+            parse: Parse::Text,
+            params: [0; MAX_PARAMS],
+            nparams: 0,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.head = 0;
+        self.count = 1;
+        self.col = 0;
+        self.lens[0] = 0;
+        self.view_offset = 0;
+    }
+
+    fn newline(&mut self) {
+        self.head = (self.head + 1) % LINES;
+        self.lens[self.head] = 0;
+        self.col = 0;
+        self.count = (self.count + 1).min(LINES);
+        // Keep a scrolled-back view on the same text while output arrives.
+        if self.view_offset > 0 {
+            self.view_offset = (self.view_offset + 1).min(self.count - 1);
+        }
+    }
+
+    fn put(&mut self, ch: u8) {
+        if self.col >= self.columns {
+            self.newline();
+        }
+        let fg = match self.fg {
+            fg if self.bright && fg < 8 => fg + 8,
+            fg => fg,
+        };
+        let head = self.head;
+        let len = self.lens[head] as usize;
+        // Gaps left by `\t` or a cursor move are blank cells.
+        for cell in self.lines[head][len.min(self.col)..self.col].iter_mut() {
+            *cell = Cell::BLANK;
+        }
+        self.lines[head][self.col] = Cell { ch, fg, bg: self.bg };
+        self.col += 1;
+        self.lens[head] = len.max(self.col) as u16;
+    }
+
+    fn sgr(&mut self) {
+        let params = &self.params[..self.nparams.max(1)];
+        for &param in params.iter() {
+            match param {
+                0 => {
+                    self.fg = DEFAULT_COLOR;
+                    self.bg = DEFAULT_COLOR;
+                    self.bright = false;
+                }
+                1 => self.bright = true,
+                22 => self.bright = false,
+                30..=37 => self.fg = (param - 30) as u8,
+                39 => self.fg = DEFAULT_COLOR,
+                40..=47 => self.bg = (param - 40) as u8,
+                49 => self.bg = DEFAULT_COLOR,
+                90..=97 => self.fg = (param - 90 + 8) as u8,
+                100..=107 => self.bg = (param - 100 + 8) as u8,
+                _ => {}
+            }
+        }
+    }
+
+    fn csi_final(&mut self, byte: u8) {
+        match byte {
+            b'm' => self.sgr(),
+            b'J' if self.params[0] == 2 => self.clear(),
+            b'K' => {
+                let head = self.head;
+                self.lens[head] = (self.lens[head] as usize).min(self.col) as u16;
+            }
+            _ => {}
+        }
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        match self.parse {
+            Parse::Escape => {
+                self.parse = if byte == b'[' { Parse::Csi } else { Parse::Text };
+                self.params = [0; MAX_PARAMS];
+                self.nparams = 0;
+            }
+            Parse::Csi => match byte {
+                b'0'..=b'9' => {
+                    let index = self.nparams.max(1) - 1;
+                    self.nparams = self.nparams.max(1);
+                    if index < MAX_PARAMS {
+                        self.params[index] = self.params[index]
+                            .saturating_mul(10)
+                            .saturating_add((byte - b'0') as u16);
+                    }
+                }
+                b';' => self.nparams = (self.nparams.max(1) + 1).min(MAX_PARAMS),
+                0x40..=0x7E => {
+                    self.csi_final(byte);
+                    self.parse = Parse::Text;
+                }
+                _ => self.parse = Parse::Text,
+            },
+            Parse::Text => match byte {
+                0x1B => self.parse = Parse::Escape,
+                b'\n' => self.newline(),
+                b'\r' => self.col = 0,
+                b'\t' => {
+                    let next = (self.col / 8 + 1) * 8;
+                    while self.col < next.min(self.columns) {
+                        self.put(b' ');
+                    }
+                }
+                0x08 => self.col = self.col.saturating_sub(1),
+                0x20..=0x7E => self.put(byte),
+                // One `?` per non-ASCII character: lead bytes only.
+                0xC0..=0xFF => self.put(b'?'),
+                _ => {}
+            },
+        }
+    }
+
+    fn slot_of(&self, line_from_bottom: usize) -> usize {
+        (self.head + LINES - line_from_bottom) % LINES
+    }
+
+    /// How far back the view can go and still fill the panel with
+    /// completed lines.
+    fn max_offset(&self) -> usize {
+        (self.count - 1).saturating_sub(self.rows)
+    }
+}
+
+static CONSOLE: SpinLock<Console<FBCON_SCROLLBACK>> = SpinLock::new(Console::new());
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Route kernel console output here instead of the firmware console. Set
+/// once boot services are gone.
+pub fn set_active(active: bool) {
+    ACTIVE.store(active, Ordering::Release);
+}
+
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::Acquire)
+}
+
+pub fn write_bytes(bytes: &[u8]) {
+    let mut console = CONSOLE.lock();
+    for &byte in bytes.iter() {
+        console.write_byte(byte);
+    }
+}
+
+pub fn write(text: &str) {
+    write_bytes(text.as_bytes());
+}
+
+/// Write `bytes` as a line of its own.
+pub fn write_line_bytes(bytes: &[u8]) {
+    let mut console = CONSOLE.lock();
+    if console.lens[console.head] != 0 {
+        console.newline();
+    }
+    for &byte in bytes.iter() {
+        console.write_byte(byte);
+    }
+    console.newline();
+}
+
+pub fn write_line(text: &str) {
+    write_line_bytes(text.as_bytes());
+}
+
+pub fn clear() {
+    CONSOLE.lock().clear();
+}
+
+pub fn page_up() {
+    let mut console = CONSOLE.lock();
+    let step = console.rows.saturating_sub(1).max(1);
+    console.view_offset = (console.view_offset + step).min(console.max_offset());
+}
+
+pub fn page_down() {
+    let mut console = CONSOLE.lock();
+    let step = console.rows.saturating_sub(1).max(1);
+    console.view_offset = console.view_offset.saturating_sub(step);
+}
+
+pub fn scroll_to_bottom() {
+    CONSOLE.lock().view_offset = 0;
+}
+
+/// Lines the view is scrolled back from the bottom.
+pub fn scrolled_lines() -> usize {
+    CONSOLE.lock().view_offset
+}
+
+/// Text of the newest `max` complete lines, oldest first, without colour.
+pub fn for_each_line<F>(max: usize, mut f: F)
+where
+    F: FnMut(&[u8]),
+{
+    let console = CONSOLE.lock();
+    let complete = console.count - 1;
+    let mut text = [0u8; FBCON_MAX_COLS];
+    for back in (1..=complete.min(max)).rev() {
+        let slot = console.slot_of(back);
+        let len = console.lens[slot] as usize;
+        for (out, cell) in text.iter_mut().zip(console.lines[slot][..len].iter()) {
+            *out = cell.ch;
+        }
+        f(&text[..len]);
+    }
+}
+
+fn draw_glyph(x: usize, y: usize, ch: u8, color: u32) {
+    let glyph = crate::font::glyph_5x7(ch as char);
+    for (row, bits) in glyph.iter().enumerate() {
+        for col in 0..5 {
+            if bits & (1 << (4 - col)) != 0 {
+                framebuffer::pixel(x + col, y + row, color);
+            }
+        }
+    }
+}
+
+/// Draw the visible part of the console into `w`x`h` pixels at (`x`, `y`)
+/// with the panel's default colours. Completed lines fill the area from the
+/// bottom; the line being written is left out, as the panel shows its own
+/// input line.
+pub fn render(x: usize, y: usize, w: usize, h: usize, fg: u32, bg: u32) {
+    let mut console = CONSOLE.lock();
+    console.columns = (w / CELL_W).clamp(1, FBCON_MAX_COLS);
+    console.rows = (h / CELL_H).max(1);
+    console.view_offset = console.view_offset.min(console.max_offset());
+    let rows = console.rows;
+    let complete = console.count - 1;
+    let visible = complete.saturating_sub(console.view_offset).min(rows);
+    for row in 0..visible {
+        let back = console.view_offset + visible - row;
+        let slot = console.slot_of(back);
+        let line_y = y + row * CELL_H;
+        for (index, cell) in console.lines[slot][..console.lens[slot] as usize].iter().enumerate() {
+            let cell_x = x + index * CELL_W;
+            if cell.bg != DEFAULT_COLOR {
+                framebuffer::rect(cell_x, line_y, CELL_W, CELL_H, PALETTE[cell.bg as usize]);
+            }
+            let color = if cell.fg == DEFAULT_COLOR {
+                fg
+            } else {
+                PALETTE[cell.fg as usize]
+            };
+            draw_glyph(cell_x, line_y + 1, cell.ch, color);
+        }
+    }
+    if console.view_offset > 0 {
+        let mut label: crate::klog::FixedBuf<24> = crate::klog::FixedBuf::new();
+        let _ = core::fmt::write(&mut label, format_args!("[SCROLL -{}]", console.view_offset));
+        let label_w = label.as_str().len() * CELL_W;
+        let label_x = (x + w).saturating_sub(label_w + 2);
+        framebuffer::rect(label_x, y, label_w + 2, CELL_H, bg);
+        for (index, byte) in label.as_str().bytes().enumerate() {
+            draw_glyph(label_x + 1 + index * CELL_W, y + 1, byte, PALETTE[11]);
+        }
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "fbcon";
+
+    fn ansi_colours_and_wrapping() {
+        let mut console: Console<8> = Console::new();
+        console.columns = 4;
+        for &byte in b"\x1b[31mab\x1b[0mcdef\n".iter() {
+            console.write_byte(byte);
+        }
+        let first = console.slot_of(2);
+        crate::selftest::ensure_eq(console.lens[first], 4, "ajuste de linea")?;
+        crate::selftest::ensure_eq(console.lines[first][0].fg, 1, "rojo")?;
+        crate::selftest::ensure_eq(console.lines[first][2].fg, DEFAULT_COLOR, "reset")?;
+        let second = console.slot_of(1);
+        crate::selftest::ensure_eq(console.lines[second][0].ch, b'e', "continuacion")?;
+        crate::selftest::ensure_eq(console.count, 3, "lineas")
+    }
+
+    fn scrollback_pages_and_clears() {
+        let mut console: Console<16> = Console::new();
+        console.rows = 4;
+        for _ in 0..40 {
+            console.write_byte(b'x');
+            console.write_byte(b'\n');
+        }
+        crate::selftest::ensure_eq(console.count, 16, "limite")?;
+        console.view_offset = 5;
+        console.write_byte(b'\n');
+        crate::selftest::ensure_eq(console.view_offset, 6, "vista anclada")?;
+        for &byte in b"\x1b[2J".iter() {
+            console.write_byte(byte);
+        }
+        crate::selftest::ensure_eq(console.count, 1, "limpio")?;
+        crate::selftest::ensure_eq(console.view_offset, 0, "abajo")
+    }
+}
@@ -1,7 +1,19 @@
+use alloc::vec::Vec;
 use core::ptr;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::spinlock::SpinLock;
+use crate::sync::KernelCell;
 
 const BACKBUFFER_CAPACITY: usize = 64 * 1024 * 1024;
 
+/// UI scale factors in percent (1x, 1.5x, 2x).
+pub const SCALE_STEPS: [u32; 3] = [100, 150, 200];
+/// Smallest logical desktop a scale factor may leave.
+const MIN_LOGICAL_W: usize = 640;
+const MIN_LOGICAL_H: usize = 400;
+const MAX_NATIVE_LAYERS: usize = 8;
+
 #[repr(align(64))]
 struct AlignedBackbuffer([u8; BACKBUFFER_CAPACITY]);
 
@@ -24,6 +36,9 @@ pub struct FramebufferInfo {
     pub layout: PixelLayout,
 }
 
+/// `width`/`height`/`stride` describe the surface drawing happens on. With a
+/// UI scale above 100% that is a smaller logical frame in the backbuffer and
+/// `present` upscales it to the `phys_*` geometry of the GOP framebuffer.
 #[derive(Clone, Copy)]
 struct Framebuffer {
     front_base: *mut u8,
@@ -34,6 +49,10 @@ struct Framebuffer {
     stride: usize,
     layout: PixelLayout,
     backbuffer_enabled: bool,
+    phys_width: usize,
+    phys_height: usize,
+    phys_stride: usize,
+    scale_pct: u32,
 }
 
 impl Framebuffer {
@@ -47,14 +66,49 @@ impl Framebuffer {
             stride: 0,
             layout: PixelLayout::Unknown,
             backbuffer_enabled: false,
+            phys_width: 0,
+            phys_height: 0,
+            phys_stride: 0,
+            scale_pct: 100,
         }
     }
 }
 
 static mut FB: Framebuffer = Framebuffer::empty();
 
+/// Device-resolution content queued by `blit_native` for the next `present`.
+struct NativeLayer {
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+    src_w: usize,
+    src_h: usize,
+    /// Source pixels already packed in the framebuffer layout.
+    pixels: Vec<u32>,
+}
+
+static mut NATIVE_LAYERS: Vec<NativeLayer> = Vec::new();
+/// Logical column for every physical column while scaled.
+static mut SCALE_COLUMNS: Vec<u32> = Vec::new();
+static mut SCALE_ROW: Vec<u32> = Vec::new();
+/// Software color correction applied by `present`, indexed by byte position
+/// within a packed pixel (so already in the framebuffer's channel order).
+static PRESENT_LUT: SpinLock<Option<[[u8; 256]; 3]>> = SpinLock::new(None);
+/// Front buffer in RAM, standing in for GOP on machines without a display.
+static RAM_FRONT: KernelCell<Vec<u32>> = KernelCell::new(Vec::new());
+static HEADLESS: AtomicBool = AtomicBool::new(false);
+
+/// `auto` (RAM framebuffer only when there is no GOP), `on` or `off`.
+pub const HEADLESS_KEY: &str = "display.headless";
+pub const HEADLESS_SIZE_KEY: &str = "display.headless_size";
+/// Largest RAM framebuffer `ram_info` hands out.
+pub const HEADLESS_MAX: (usize, usize) = (3840, 2160);
+
 pub fn init(info: FramebufferInfo) {
     unsafe {
+        let ram = RAM_FRONT.get();
+        HEADLESS.store(!ram.is_empty() && info.base == ram.as_ptr() as *mut u8, Ordering::Relaxed);
         FB = Framebuffer {
             front_base: info.base,
             draw_base: info.base,
@@ -64,8 +118,53 @@ pub fn init(info: FramebufferInfo) {
             stride: info.stride,
             layout: info.layout,
             backbuffer_enabled: false,
+            phys_width: info.width,
+            phys_height: info.height,
+            phys_stride: info.stride,
+            scale_pct: 100,
         };
+        (*ptr::addr_of_mut!(NATIVE_LAYERS)).clear();
     }
+    let driver = if is_headless() { "ram" } else { "gop" };
+    let node = crate::device::add(None, "fb0", Some("graphics"), Some(driver));
+    crate::device::set_live(node, "mode", || {
+        let (width, height) = dimensions();
+        alloc::format!("{}x{}", width, height)
+    });
+}
+
+/// A framebuffer in RAM for headless machines (no GOP, broken GPU, CI). The
+/// desktop draws and presents into it as usual; it is only seen through
+/// `capture`: screenshots and the remote view in `net::remote`.
+pub fn ram_info(width: usize, height: usize) -> FramebufferInfo {
+    let width = width.clamp(MIN_LOGICAL_W, HEADLESS_MAX.0);
+    let height = height.clamp(MIN_LOGICAL_H, HEADLESS_MAX.1);
+    unsafe {
+        let ram = RAM_FRONT.get_mut();
+        ram.clear();
+        ram.resize(width * height, 0);
+        FramebufferInfo {
+            base: ram.as_mut_ptr() as *mut u8,
+            size: width * height * 4,
+            width,
+            height,
+            stride: width,
+            // 0x00RRGGBB words are B, G, R, 0 in memory.
+            layout: PixelLayout::Bgr,
+        }
+    }
+}
+
+/// Whether the current framebuffer is the RAM one from `ram_info`.
+pub fn is_headless() -> bool {
+    HEADLESS.load(Ordering::Relaxed)
+}
+
+/// Parse a `WIDTHxHEIGHT` mode such as `1024x768`.
+pub fn parse_mode(text: &str) -> Option<(usize, usize)> {
+    let (w, h) = text.trim().split_once(['x', 'X'])?;
+    let (w, h) = (w.trim().parse::<usize>().ok()?, h.trim().parse::<usize>().ok()?);
+    (w > 0 && h > 0).then_some((w, h))
 }
 
 pub fn dimensions() -> (usize, usize) {
@@ -77,6 +176,7 @@ pub fn enable_backbuffer() -> bool {
         if FB.size == 0 || FB.front_base.is_null() || FB.size > BACKBUFFER_CAPACITY {
             FB.draw_base = FB.front_base;
             FB.backbuffer_enabled = false;
+            apply_scale_geometry();
             return false;
         }
 
@@ -85,10 +185,67 @@ pub fn enable_backbuffer() -> bool {
 
         FB.draw_base = back_ptr;
         FB.backbuffer_enabled = true;
+        apply_scale_geometry();
         true
     }
 }
 
+/// Logical size of a `width`x`height` screen at `scale_pct`.
+pub const fn scaled_size(width: usize, height: usize, scale_pct: u32) -> (usize, usize) {
+    (width * 100 / scale_pct as usize, height * 100 / scale_pct as usize)
+}
+
+unsafe fn apply_scale_geometry() {
+    if FB.scale_pct == 100 || !FB.backbuffer_enabled {
+        FB.scale_pct = 100;
+        FB.width = FB.phys_width;
+        FB.height = FB.phys_height;
+        FB.stride = FB.phys_stride;
+        return;
+    }
+    let (w, h) = scaled_size(FB.phys_width, FB.phys_height, FB.scale_pct);
+    FB.width = w;
+    FB.height = h;
+    FB.stride = w;
+
+    let columns = &mut *ptr::addr_of_mut!(SCALE_COLUMNS);
+    columns.clear();
+    columns.extend((0..FB.phys_width).map(|px| (px * w / FB.phys_width) as u32));
+    let row = &mut *ptr::addr_of_mut!(SCALE_ROW);
+    row.clear();
+    row.resize(FB.phys_width, 0);
+}
+
+/// Switch the UI scale. Drawing after this uses the returned logical size;
+/// callers that cached `dimensions()` must relayout.
+pub fn set_scale_percent(scale_pct: u32) -> Result<(usize, usize), &'static str> {
+    if !SCALE_STEPS.contains(&scale_pct) {
+        return Err("escala no soportada (1x, 1.5x, 2x)");
+    }
+    unsafe {
+        if scale_pct != 100 && !FB.backbuffer_enabled {
+            return Err("la escala requiere backbuffer");
+        }
+        let (w, h) = scaled_size(FB.phys_width, FB.phys_height, scale_pct);
+        if scale_pct != 100 && (w < MIN_LOGICAL_W || h < MIN_LOGICAL_H) {
+            return Err("pantalla demasiado pequena para esa escala");
+        }
+        FB.scale_pct = scale_pct;
+        apply_scale_geometry();
+        (*ptr::addr_of_mut!(NATIVE_LAYERS)).clear();
+    }
+    Ok(dimensions())
+}
+
+/// Device pixels per logical pixel, in percent (100 = 1x).
+pub fn scale_percent() -> u32 {
+    unsafe { FB.scale_pct }
+}
+
+pub fn physical_dimensions() -> (usize, usize) {
+    unsafe { (FB.phys_width, FB.phys_height) }
+}
+
 pub fn backbuffer_enabled() -> bool {
     unsafe { FB.backbuffer_enabled }
 }
@@ -97,13 +254,194 @@ pub fn draw_base() -> *mut u8 {
     unsafe { FB.draw_base }
 }
 
+/// The logical drawing surface as 0xRRGGBB pixels, row by row from the top.
+pub fn capture() -> Vec<u32> {
+    unsafe {
+        let mut out = Vec::new();
+        if FB.draw_base.is_null() {
+            return out;
+        }
+        out.reserve(FB.width * FB.height);
+        for y in 0..FB.height {
+            for x in 0..FB.width {
+                let ptr = FB.draw_base.add((y * FB.stride + x) * 4);
+                let (c0, c1, c2) = (ptr.read_volatile(), ptr.add(1).read_volatile(), ptr.add(2).read_volatile());
+                let (r, g, b) = match FB.layout {
+                    PixelLayout::Rgb => (c0, c1, c2),
+                    PixelLayout::Bgr | PixelLayout::Unknown => (c2, c1, c0),
+                };
+                out.push(((r as u32) << 16) | ((g as u32) << 8) | b as u32);
+            }
+        }
+        out
+    }
+}
+
 pub fn present() {
     unsafe {
         if !FB.backbuffer_enabled || FB.front_base.is_null() || FB.draw_base.is_null() {
             return;
         }
 
-        ptr::copy_nonoverlapping(FB.draw_base as *const u8, FB.front_base, FB.size);
+        if FB.scale_pct != 100 {
+            present_scaled();
+            (*ptr::addr_of_mut!(NATIVE_LAYERS)).clear();
+            return;
+        }
+
+        // Copied out so the lock (and the interrupt mask) is not held for the whole frame.
+        let lut = *PRESENT_LUT.lock();
+        match lut.as_ref() {
+            Some(lut) => {
+                let back = FB.draw_base as *const u32;
+                let front = FB.front_base as *mut u32;
+                for y in 0..FB.height {
+                    let offset = y * FB.stride;
+                    shade_row(lut, back.add(offset), front.add(offset), FB.width);
+                }
+            }
+            None => ptr::copy_nonoverlapping(FB.draw_base as *const u8, FB.front_base, FB.size),
+        }
+    }
+}
+
+/// Set (or clear) the per-channel ramp `present` applies on its way to the
+/// front buffer. Only takes effect with the backbuffer enabled.
+pub fn set_color_lut(lut: Option<(&[u8; 256], &[u8; 256], &[u8; 256])>) {
+    let layout = unsafe { FB.layout };
+    *PRESENT_LUT.lock() = lut.map(|(r, g, b)| match layout {
+        PixelLayout::Rgb => [*r, *g, *b],
+        PixelLayout::Bgr | PixelLayout::Unknown => [*b, *g, *r],
+    });
+}
+
+/// Copy `len` pixels from `src` to `dst` through the color LUT.
+unsafe fn shade_row(lut: &[[u8; 256]; 3], src: *const u32, dst: *mut u32, len: usize) {
+    let mut i = 0usize;
+    while i < len {
+        let p = *src.add(i);
+        let c0 = lut[0][(p & 0xFF) as usize] as u32;
+        let c1 = lut[1][((p >> 8) & 0xFF) as usize] as u32;
+        let c2 = lut[2][((p >> 16) & 0xFF) as usize] as u32;
+        *dst.add(i) = (p & 0xFF00_0000) | c2 << 16 | c1 << 8 | c0;
+        i += 1;
+    }
+}
+
+/// Nearest-neighbour upscale of the logical frame, one physical row at a time.
+/// Rows that map to the same logical row reuse the expanded scratch row unless
+/// a native layer touches them.
+unsafe fn present_scaled() {
+    let columns = &*ptr::addr_of!(SCALE_COLUMNS);
+    let row = &mut *ptr::addr_of_mut!(SCALE_ROW);
+    let layers = &*ptr::addr_of!(NATIVE_LAYERS);
+    let lut = *PRESENT_LUT.lock();
+    if columns.len() != FB.phys_width || row.len() != FB.phys_width {
+        return;
+    }
+    let back = FB.draw_base as *const u32;
+    let front = FB.front_base as *mut u32;
+    let mut built_for = usize::MAX;
+    let mut py = 0usize;
+    while py < FB.phys_height {
+        let sy = py * FB.height / FB.phys_height;
+        let layered = layers.iter().any(|layer| sy >= layer.y && sy < layer.y + layer.h);
+        if sy != built_for || layered {
+            let src = back.add(sy * FB.stride);
+            let mut px = 0usize;
+            while px < FB.phys_width {
+                row[px] = *src.add(columns[px] as usize);
+                px += 1;
+            }
+            built_for = if layered { usize::MAX } else { sy };
+            for layer in layers.iter() {
+                compose_native_row(layer, py, sy, columns, row.as_mut_slice(), back);
+            }
+        }
+        match lut.as_ref() {
+            Some(lut) => shade_row(lut, row.as_ptr(), front.add(py * FB.phys_stride), FB.phys_width),
+            None => ptr::copy_nonoverlapping(row.as_ptr(), front.add(py * FB.phys_stride), FB.phys_width),
+        }
+        py += 1;
+    }
+}
+
+/// First physical coordinate whose logical coordinate is `>= logical`.
+fn physical_start(logical: usize, logical_len: usize, physical_len: usize) -> usize {
+    (logical * physical_len).div_ceil(logical_len).min(physical_len)
+}
+
+/// Replace the pixels of `row` covered by `layer` with device-resolution
+/// samples, but only where the logical frame still shows the layer's own
+/// downscaled content (anything drawn over it since stays on top).
+unsafe fn compose_native_row(
+    layer: &NativeLayer,
+    py: usize,
+    sy: usize,
+    columns: &[u32],
+    row: &mut [u32],
+    back: *const u32,
+) {
+    if sy < layer.y || sy >= layer.y + layer.h {
+        return;
+    }
+    let px0 = physical_start(layer.x, FB.width, FB.phys_width);
+    let px1 = physical_start(layer.x + layer.w, FB.width, FB.phys_width);
+    let py0 = physical_start(layer.y, FB.height, FB.phys_height);
+    let py1 = physical_start(layer.y + layer.h, FB.height, FB.phys_height);
+    if px1 <= px0 || py1 <= py0 {
+        return;
+    }
+    let logical_src_row = (sy - layer.y) * layer.src_h / layer.h * layer.src_w;
+    let native_src_row = (py - py0) * layer.src_h / (py1 - py0) * layer.src_w;
+    let shown = back.add(sy * FB.stride);
+    let mut px = px0;
+    while px < px1 {
+        let lx = columns[px] as usize;
+        let expected = layer.pixels[logical_src_row + (lx - layer.x) * layer.src_w / layer.w];
+        if *shown.add(lx) == expected {
+            row[px] = layer.pixels[native_src_row + (px - px0) * layer.src_w / (px1 - px0)];
+        }
+        px += 1;
+    }
+}
+
+/// Show `src` (`src_w`x`src_h`) in the logical rect at device resolution.
+/// The caller must already have drawn the same image into that rect at
+/// logical resolution (nearest sampling, as `Window` does); that copy is what
+/// stays visible at 1x and wherever something is drawn over it later.
+pub fn blit_native(x: usize, y: usize, w: usize, h: usize, src_w: usize, src_h: usize, src: &[u32]) {
+    unsafe {
+        if FB.scale_pct == 100 || w == 0 || h == 0 || src_w == 0 || src_h == 0 {
+            return;
+        }
+        if src.len() < src_w * src_h || x + w > FB.width || y + h > FB.height {
+            return;
+        }
+        let layers = &mut *ptr::addr_of_mut!(NATIVE_LAYERS);
+        if layers.len() >= MAX_NATIVE_LAYERS {
+            return;
+        }
+        layers.push(NativeLayer {
+            x,
+            y,
+            w,
+            h,
+            src_w,
+            src_h,
+            pixels: src[..src_w * src_h].iter().map(|&color| packed(color)).collect(),
+        });
+    }
+}
+
+/// `color` (0xRRGGBB) as stored in the backbuffer.
+fn packed(color: u32) -> u32 {
+    let (r, g, b) = split_rgb(color);
+    unsafe {
+        match FB.layout {
+            PixelLayout::Rgb => (r as u32) | ((g as u32) << 8) | ((b as u32) << 16),
+            PixelLayout::Bgr | PixelLayout::Unknown => (b as u32) | ((g as u32) << 8) | ((r as u32) << 16),
+        }
     }
 }
 
@@ -291,6 +629,83 @@ pub fn blit(x: usize, y: usize, w: usize, h: usize, buffer: &[u32]) {
     }
 }
 
+/// Mix two packed pixels channel by channel: `alpha` 0 keeps `dst`, 255
+/// takes `src`.
+pub fn blend_packed(dst: u32, src: u32, alpha: u8) -> u32 {
+    let a = alpha as u32 + (alpha >> 7) as u32;
+    let mix = |shift: u32| {
+        let d = (dst >> shift) & 0xFF;
+        let s = (src >> shift) & 0xFF;
+        ((s * a + d * (256 - a)) >> 8) << shift
+    };
+    mix(0) | mix(8) | mix(16)
+}
+
+#[inline]
+unsafe fn blend_at(p: *mut u32, src: u32, alpha: u8) {
+    if alpha == u8::MAX {
+        if FB.backbuffer_enabled {
+            p.write(src);
+        } else {
+            p.write_volatile(src);
+        }
+    } else if FB.backbuffer_enabled {
+        p.write(blend_packed(p.read(), src, alpha));
+    } else {
+        p.write_volatile(blend_packed(p.read_volatile(), src, alpha));
+    }
+}
+
+/// Fill with `color` at `alpha` over what is already drawn. Without the
+/// backbuffer this reads the GOP framebuffer back, which is slow.
+pub fn blend_rect(x: usize, y: usize, w: usize, h: usize, color: u32, alpha: u8) {
+    if w == 0 || h == 0 || alpha == 0 {
+        return;
+    }
+    unsafe {
+        if FB.draw_base.is_null() {
+            return;
+        }
+        let max_x = x.saturating_add(w).min(FB.width);
+        let max_y = y.saturating_add(h).min(FB.height);
+        let src = packed(color);
+        for yy in y..max_y {
+            let row = FB.draw_base.add(yy * FB.stride * 4) as *mut u32;
+            for xx in x..max_x {
+                blend_at(row.add(xx), src, alpha);
+            }
+        }
+    }
+}
+
+/// Draw `src` (0xRRGGBB rows of `src_w` pixels) stretched to `w` x `h` at
+/// (`x`, `y`), nearest neighbour, mixed at `alpha` over what is already
+/// drawn. Parts off screen are clipped.
+pub fn blit_scaled_blend(x: i32, y: i32, w: usize, h: usize, src: &[u32], src_w: usize, alpha: u8) {
+    let src_h = if src_w == 0 { 0 } else { src.len() / src_w };
+    if w == 0 || h == 0 || src_h == 0 || alpha == 0 {
+        return;
+    }
+    unsafe {
+        if FB.draw_base.is_null() {
+            return;
+        }
+        let x0 = x.max(0) as usize;
+        let y0 = y.max(0) as usize;
+        let max_x = ((x as i64 + w as i64).max(0) as usize).min(FB.width);
+        let max_y = ((y as i64 + h as i64).max(0) as usize).min(FB.height);
+        for yy in y0..max_y {
+            let sy = (yy as i64 - y as i64) as usize * src_h / h;
+            let src_row = &src[sy * src_w..(sy + 1) * src_w];
+            let row = FB.draw_base.add(yy * FB.stride * 4) as *mut u32;
+            for xx in x0..max_x {
+                let sx = (xx as i64 - x as i64) as usize * src_w / w;
+                blend_at(row.add(xx), packed(src_row[sx]), alpha);
+            }
+        }
+    }
+}
+
 pub fn digit_7seg(x: usize, y: usize, scale: usize, value: u8, color: u32) {
     let scale = scale.max(1);
     let t = scale;
@@ -1,5 +1,7 @@
 use core::str;
 
+pub mod vfs;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FileType {
     File,
@@ -44,7 +46,7 @@ impl DirEntry {
         unsafe { str::from_utf8_unchecked(&self.name[0..len]) }.trim()
     }
 
-    fn short_name(&self) -> alloc::string::String {
+    pub fn short_name(&self) -> alloc::string::String {
         let mut name = alloc::string::String::new();
         // Name part (indices 0..8)
         let name_part = &self.name[0..8];
@@ -100,9 +102,3 @@ pub trait FileSystem {
     fn read_dir(&mut self, cluster: u32) -> Result<[DirEntry; 16], &'static str>; // Fixed size for now
     fn read_file(&mut self, cluster: u32, buffer: &mut [u8]) -> Result<usize, &'static str>;
 }
-
-// Global VFS instance (simplified)
-pub struct Vfs {
-    // In a real OS, this would be dynamic.
-    // Here we will just hold a single optional FS.
-}
@@ -0,0 +1,1203 @@
+//! Virtual filesystem: one namespace over every mounted filesystem.
+//!
+//! The mount table maps absolute paths to backends. `/` is the working
+//! volume (`crate::fat32::GLOBAL_FAT`, which `mount <n>` still switches),
+//! `/host` the virtio-9p export, `/sys` the device model and `/dev` the
+//! block devices. `mount /dev/nvme0p1 /data` attaches another FAT32/exFAT
+//! volume with a `Fat32` of its own. A path belongs to the mount with the
+//! longest matching prefix, compared without case as FAT names are, so
+//! `/data/DOCS/A.TXT` is `DOCS/A.TXT` on that volume.
+//!
+//! `open` hands out a `Handle` with `read`, `write`, `seek`, `stat` and
+//! `close`. FAT files opened for reading are read straight from their
+//! clusters; a file opened for writing is kept in memory and stored with one
+//! whole-file write on `flush` or `close`, the way every other writer in the
+//! kernel updates a file. It is held in `CHUNK` pieces, so a hole left by a
+//! write past the end costs nothing until it is stored, and it may not grow
+//! past what FAT32 can record or the volume has free. The terminal, the Linux shim and the file dialogs
+//! all come through here for paths below a mount.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::fat32::{Fat32, InitStatus, GLOBAL_FAT};
+use crate::fs::{FileSystem, FileType};
+use crate::hostfs::{self, HOST_MOUNT};
+use crate::sync::KernelCell;
+use crate::sysfs::{self, SYS_MOUNT};
+use crate::virtio::p9;
+
+pub const DEV_MOUNT: &str = "/dev";
+
+const CAT_LIMIT: usize = 16 * 1024;
+const MAX_HANDLES: usize = 64;
+/// Bytes per piece of a file held in memory.
+const CHUNK: usize = 64 * 1024;
+/// Largest size FAT32 records. Host files are held to it too, as they are
+/// also written whole from memory.
+const MAX_FILE_SIZE: u64 = u32::MAX as u64;
+
+/// A write would take the file past `MAX_FILE_SIZE`.
+pub const TOO_BIG: &str = "Archivo demasiado grande.";
+/// A write would take the file past the free space of its volume, or past
+/// the memory left to hold it.
+pub const NO_SPACE: &str = "No queda espacio.";
+
+const NOT_FOUND: &str = "No existe.";
+const READ_ONLY: &str = "Sistema de archivos de solo lectura.";
+const IS_DIR: &str = "Es un directorio.";
+const NO_VOLUME: &str = "Volumen no disponible. Usa 'disks' y 'mount <n>'.";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FsKind {
+    Fat,
+    Host,
+    Sys,
+    Dev,
+}
+
+impl FsKind {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Fat => "fat",
+            Self::Host => "9p",
+            Self::Sys => "sysfs",
+            Self::Dev => "devfs",
+        }
+    }
+}
+
+enum Backend {
+    /// The working volume in `GLOBAL_FAT`.
+    Root,
+    Fat(Box<Fat32>),
+    Host,
+    Sys,
+    Dev,
+}
+
+impl Backend {
+    fn kind(&self) -> FsKind {
+        match self {
+            Self::Root | Self::Fat(_) => FsKind::Fat,
+            Self::Host => FsKind::Host,
+            Self::Sys => FsKind::Sys,
+            Self::Dev => FsKind::Dev,
+        }
+    }
+}
+
+struct Mount {
+    path: String,
+    source: String,
+    backend: Backend,
+}
+
+impl Mount {
+    fn info(&self) -> MountInfo {
+        MountInfo {
+            path: self.path.clone(),
+            source: self.source.clone(),
+            kind: self.backend.kind(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MountInfo {
+    pub path: String,
+    pub source: String,
+    pub kind: FsKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stat {
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+static MOUNTS: KernelCell<Vec<Mount>> = KernelCell::new(Vec::new());
+
+/// The mount table, with the built-in mounts on first use.
+///
+/// # Safety
+///
+/// Kernel thread only, like `GLOBAL_FAT` itself, and no earlier borrow from
+/// `table` may still be in use: callers take it once and finish with it
+/// before calling anything that takes it again.
+unsafe fn table() -> &'static mut Vec<Mount> {
+    let mounts = unsafe { MOUNTS.get_mut() };
+    if mounts.is_empty() {
+        let builtin = [
+            ("/", "root", Backend::Root),
+            (HOST_MOUNT, "host", Backend::Host),
+            (SYS_MOUNT, "sys", Backend::Sys),
+            (DEV_MOUNT, "dev", Backend::Dev),
+        ];
+        for (path, source, backend) in builtin {
+            mounts.push(Mount {
+                path: String::from(path),
+                source: String::from(source),
+                backend,
+            });
+        }
+    }
+    mounts
+}
+
+/// `path` from the root, with `.` and `..` resolved and no trailing '/'.
+pub fn normalize(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.trim().split(['/', '\\']) {
+        match part.trim() {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            name => parts.push(name),
+        }
+    }
+    if parts.is_empty() {
+        return String::from("/");
+    }
+    let mut out = String::new();
+    for part in parts {
+        out.push('/');
+        out.push_str(part);
+    }
+    out
+}
+
+/// `dir` and `name` joined and normalized.
+pub fn join(dir: &str, name: &str) -> String {
+    normalize(alloc::format!("{}/{}", dir, name).as_str())
+}
+
+/// What is left of normalized `path` below mount point `mount`.
+fn relative<'a>(path: &'a str, mount: &str) -> Option<&'a str> {
+    if mount == "/" {
+        return Some(path.trim_start_matches('/'));
+    }
+    let head = path.get(..mount.len())?;
+    if !head.eq_ignore_ascii_case(mount) {
+        return None;
+    }
+    match &path[mount.len()..] {
+        "" => Some(""),
+        rest => rest.strip_prefix('/'),
+    }
+}
+
+/// Index in `mounts` of the mount holding normalized `path`, and the path
+/// inside it.
+fn locate(mounts: &[Mount], path: &str) -> (usize, String) {
+    let mut best: (usize, usize, &str) = (0, 0, path.trim_start_matches('/'));
+    for (index, mount) in mounts.iter().enumerate() {
+        if let Some(rel) = relative(path, &mount.path) {
+            if mount.path.len() > best.1 {
+                best = (index, mount.path.len(), rel);
+            }
+        }
+    }
+    (best.0, String::from(best.2))
+}
+
+/// Kind of the mount holding `path`, or `None` when it is on the working
+/// volume or not absolute.
+pub fn mount_kind(path: &str) -> Option<FsKind> {
+    if !path.trim().starts_with('/') {
+        return None;
+    }
+    // SAFETY: kernel thread; the borrow ends with this function.
+    let mounts = unsafe { table() };
+    let (index, _) = locate(mounts, &normalize(path));
+    (index != 0).then(|| mounts[index].backend.kind())
+}
+
+pub fn is_mounted_path(path: &str) -> bool {
+    mount_kind(path).is_some()
+}
+
+pub fn mounts() -> Vec<MountInfo> {
+    // SAFETY: kernel thread; the borrow ends with the statement.
+    let mut out: Vec<MountInfo> = unsafe { table() }.iter().map(Mount::info).collect();
+    out.sort_by(|a, b| a.path.cmp(&b.path));
+    out
+}
+
+/// Mounts that hold user files (other volumes, and `/host` when the export
+/// is there), for file dialogs.
+pub fn volume_mounts() -> Vec<MountInfo> {
+    // SAFETY: kernel thread; the borrow ends with the statement.
+    unsafe { table() }
+        .iter()
+        .skip(1)
+        .filter(|m| match m.backend {
+            Backend::Fat(_) => true,
+            Backend::Host => crate::virtio::p9::is_mounted(),
+            _ => false,
+        })
+        .map(Mount::info)
+        .collect()
+}
+
+/// The working volume, mounted on first use.
+///
+/// # Safety
+///
+/// Kernel thread only, and no other `GLOBAL_FAT` borrow may be in use while
+/// the returned one is.
+unsafe fn root_volume() -> Result<&'static mut Fat32, &'static str> {
+    let fat = unsafe { GLOBAL_FAT.get_mut() };
+    if fat.init_status != InitStatus::Success {
+        fat.init();
+    }
+    if fat.init_status != InitStatus::Success {
+        return Err(NO_VOLUME);
+    }
+    Ok(fat)
+}
+
+/// The volume behind the FAT mount at `mount`.
+///
+/// # Safety
+///
+/// As for `root_volume`, when `mount` is `/`.
+unsafe fn volume_at<'a>(mounts: &'a mut [Mount], mount: &str) -> Result<&'a mut Fat32, &'static str> {
+    match mounts.iter_mut().find(|m| m.path == mount).map(|m| &mut m.backend) {
+        Some(Backend::Root) => unsafe { root_volume() },
+        Some(Backend::Fat(fat)) => Ok(fat),
+        _ => Err("El montaje ya no existe."),
+    }
+}
+
+fn split_parent(rel: &str) -> (&str, &str) {
+    rel.rsplit_once('/').unwrap_or(("", rel))
+}
+
+fn sys_path(rel: &str) -> String {
+    alloc::format!("{}/{}", SYS_MOUNT, rel)
+}
+
+// FAT volumes. Paths go through `crate::fsmeta` so links work, and through the
+// `Fat32` mutators so `crate::perm` and `crate::fswatch` see every change.
+
+fn fat_resolve(fat: &mut Fat32, rel: &str, follow_last: bool) -> Result<crate::fsmeta::Resolved, &'static str> {
+    let root = fat.root_cluster;
+    crate::fsmeta::resolve(fat, root, alloc::format!("/{}", rel).as_str(), follow_last)
+}
+
+fn fat_dir(fat: &mut Fat32, rel: &str) -> Result<u32, &'static str> {
+    let root = fat.root_cluster;
+    crate::fsmeta::resolve_dir(fat, root, alloc::format!("/{}", rel).as_str())
+}
+
+fn fat_stat(fat: &mut Fat32, rel: &str) -> Result<Stat, &'static str> {
+    if rel.is_empty() {
+        return Ok(Stat { is_dir: true, size: 0 });
+    }
+    let entry = fat_resolve(fat, rel, true)?.entry.ok_or(NOT_FOUND)?;
+    Ok(Stat {
+        is_dir: entry.file_type == FileType::Directory,
+        size: entry.size as u64,
+    })
+}
+
+fn fat_read_dir(fat: &mut Fat32, rel: &str) -> Result<Vec<Entry>, &'static str> {
+    let dir = fat_dir(fat, rel)?;
+    crate::perm::authorize_list(fat, dir)?;
+    Ok(fat
+        .read_dir_entries(dir)?
+        .iter()
+        .filter(|e| e.valid && !e.matches_name(".") && !e.matches_name(".."))
+        .map(|e| Entry {
+            name: e.full_name(),
+            is_dir: e.file_type == FileType::Directory,
+            size: e.size as u64,
+        })
+        .collect())
+}
+
+fn fat_read(fat: &mut Fat32, rel: &str) -> Result<Vec<u8>, &'static str> {
+    let place = fat_resolve(fat, rel, true)?;
+    match place.entry {
+        Some(entry) if entry.file_type == FileType::Directory => Err(IS_DIR),
+        Some(_) => fat.read_file_in_dir(place.dir, &place.name),
+        None => Err(NOT_FOUND),
+    }
+}
+
+/// First cluster and size of a file to read in place.
+fn fat_open(fat: &mut Fat32, rel: &str) -> Result<(u32, u64), &'static str> {
+    let place = fat_resolve(fat, rel, true)?;
+    let entry = place.entry.ok_or(NOT_FOUND)?;
+    crate::perm::authorize_open(fat, place.dir, &place.name)?;
+    Ok((entry.cluster, entry.size as u64))
+}
+
+fn fat_write(fat: &mut Fat32, rel: &str, data: &[u8]) -> Result<(), &'static str> {
+    let (parent, name) = split_parent(rel);
+    let dir = fat_dir(fat, parent)?;
+    fat.write_text_file_in_dir(dir, name, data)
+}
+
+fn fat_mkdir(fat: &mut Fat32, rel: &str) -> Result<(), &'static str> {
+    if fat_stat(fat, rel).is_ok() {
+        return Err("Ya existe.");
+    }
+    let (parent, name) = split_parent(rel);
+    let dir = fat_dir(fat, parent)?;
+    fat.ensure_subdirectory(dir, name).map(|_| ())
+}
+
+fn fat_remove(fat: &mut Fat32, rel: &str) -> Result<(), &'static str> {
+    let place = fat_resolve(fat, rel, false)?;
+    match place.entry {
+        Some(entry) if entry.file_type == FileType::Directory => fat.delete_directory_in_dir(place.dir, &place.name),
+        Some(_) => fat.delete_file_in_dir(place.dir, &place.name),
+        None => Err(NOT_FOUND),
+    }
+}
+
+// `/dev`: one read-only file per `disks` entry.
+
+pub struct BlockDevice {
+    pub name: String,
+    /// Index for `Fat32::mount_uefi_block_device`, as `disks` shows it.
+    pub index: usize,
+    pub size: u64,
+    pub mountable: bool,
+}
+
+/// Names for `disks` entries, given (removable, partition) for each in
+/// order: internal disks count up as `nvme0`, `nvme1` whatever their bus,
+/// removable ones as `usb0`, and a partition takes the disk listed before it
+/// (`nvme0p1`, `nvme0p2`).
+fn device_names(devices: &[(bool, bool)]) -> Vec<String> {
+    let mut next = [0usize; 2];
+    let mut disks: [Option<(String, usize)>; 2] = [None, None];
+    let mut names = Vec::with_capacity(devices.len());
+    for &(removable, partition) in devices {
+        let kind = removable as usize;
+        if !partition || disks[kind].is_none() {
+            let prefix = if removable { "usb" } else { "nvme" };
+            let disk = alloc::format!("{}{}", prefix, next[kind]);
+            next[kind] += 1;
+            if !partition {
+                names.push(disk.clone());
+            }
+            disks[kind] = Some((disk, 0));
+        }
+        if let (true, Some((disk, parts))) = (partition, disks[kind].as_mut()) {
+            *parts += 1;
+            names.push(alloc::format!("{}p{}", disk, parts));
+        }
+    }
+    names
+}
+
+pub fn block_devices() -> Vec<BlockDevice> {
+    let devices = Fat32::detect_uefi_block_devices();
+    let shape: Vec<(bool, bool)> = devices.iter().map(|d| (d.removable, d.logical_partition)).collect();
+    devices
+        .iter()
+        .zip(device_names(&shape))
+        .map(|(dev, name)| BlockDevice {
+            name,
+            index: dev.index,
+            size: dev.total_mib.saturating_mul(1024 * 1024),
+            mountable: dev.fs_kind.is_mountable(),
+        })
+        .collect()
+}
+
+/// `disks` index for `/dev/nvme0p1`, `nvme0p1`, `/dev/disk3` or `3`.
+fn device_index(source: &str) -> Result<usize, &'static str> {
+    let name = source.trim();
+    let name = name.strip_prefix("/dev/").unwrap_or(name);
+    if let Ok(index) = name.strip_prefix("disk").unwrap_or(name).parse::<usize>() {
+        return Ok(index);
+    }
+    block_devices()
+        .into_iter()
+        .find(|d| d.name.eq_ignore_ascii_case(name))
+        .map(|d| d.index)
+        .ok_or("Dispositivo desconocido (mira 'ls /dev').")
+}
+
+fn dev_stat(rel: &str) -> Result<Stat, &'static str> {
+    if rel.is_empty() {
+        return Ok(Stat { is_dir: true, size: 0 });
+    }
+    block_devices()
+        .into_iter()
+        .find(|d| d.name.eq_ignore_ascii_case(rel))
+        .map(|d| Stat {
+            is_dir: false,
+            size: d.size,
+        })
+        .ok_or(NOT_FOUND)
+}
+
+fn sys_stat(rel: &str) -> Result<Stat, &'static str> {
+    match sysfs::resolve(&sys_path(rel)) {
+        Some(sysfs::Node::Dir(_)) => Ok(Stat { is_dir: true, size: 0 }),
+        Some(sysfs::Node::File(text)) => Ok(Stat {
+            is_dir: false,
+            size: text.len() as u64,
+        }),
+        None => Err(NOT_FOUND),
+    }
+}
+
+pub fn stat(path: &str) -> Result<Stat, &'static str> {
+    // SAFETY: kernel thread; the backends below never come back into the VFS,
+    // so these are the only borrows of the table and the working volume.
+    let mounts = unsafe { table() };
+    let (index, rel) = locate(mounts, &normalize(path));
+    match &mut mounts[index].backend {
+        Backend::Root => fat_stat(unsafe { root_volume() }?, &rel),
+        Backend::Fat(fat) => fat_stat(fat, &rel),
+        Backend::Host => hostfs::stat(&rel)
+            .map(|e| Stat {
+                is_dir: e.is_dir,
+                size: e.size,
+            })
+            .map_err(|e| if e == p9::NOT_FOUND { NOT_FOUND } else { e }),
+        Backend::Sys => sys_stat(&rel),
+        Backend::Dev => dev_stat(&rel),
+    }
+}
+
+/// Entries of the folder at `path`, mount points below it included.
+pub fn read_dir(path: &str) -> Result<Vec<Entry>, &'static str> {
+    let path = normalize(path);
+    // SAFETY: kernel thread; the backends below never come back into the VFS,
+    // so these are the only borrows of the table and the working volume.
+    let mounts = unsafe { table() };
+    let (index, rel) = locate(mounts, &path);
+    let mut entries = match &mut mounts[index].backend {
+        Backend::Root => fat_read_dir(unsafe { root_volume() }?, &rel)?,
+        Backend::Fat(fat) => fat_read_dir(fat, &rel)?,
+        Backend::Host => hostfs::list_dir(&rel)?
+            .into_iter()
+            .map(|e| Entry {
+                name: e.name,
+                is_dir: e.is_dir,
+                size: e.size,
+            })
+            .collect(),
+        Backend::Sys => match sysfs::resolve(&sys_path(&rel)) {
+            Some(sysfs::Node::Dir(list)) => list
+                .into_iter()
+                .map(|e| Entry {
+                    name: e.name,
+                    is_dir: e.is_dir,
+                    size: 0,
+                })
+                .collect(),
+            Some(sysfs::Node::File(_)) => return Err("No es un directorio."),
+            None => return Err(NOT_FOUND),
+        },
+        Backend::Dev if rel.is_empty() => block_devices()
+            .into_iter()
+            .map(|d| Entry {
+                name: d.name,
+                is_dir: false,
+                size: d.size,
+            })
+            .collect(),
+        Backend::Dev => return Err("No es un directorio."),
+    };
+    for mount in mounts.iter().skip(1) {
+        let (parent, name) = mount.path.rsplit_once('/').unwrap_or(("", mount.path.as_str()));
+        let parent = if parent.is_empty() { "/" } else { parent };
+        if parent.eq_ignore_ascii_case(&path) && !entries.iter().any(|e| e.name.eq_ignore_ascii_case(name)) {
+            entries.push(Entry {
+                name: String::from(name),
+                is_dir: true,
+                size: 0,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Whole contents of the file at `path`.
+pub fn read(path: &str) -> Result<Vec<u8>, &'static str> {
+    // SAFETY: kernel thread; the backends below never come back into the VFS,
+    // so these are the only borrows of the table and the working volume.
+    let mounts = unsafe { table() };
+    let (index, rel) = locate(mounts, &normalize(path));
+    match &mut mounts[index].backend {
+        Backend::Root => fat_read(unsafe { root_volume() }?, &rel),
+        Backend::Fat(fat) => fat_read(fat, &rel),
+        Backend::Host => hostfs::read_file(&rel),
+        Backend::Sys => match sysfs::resolve(&sys_path(&rel)) {
+            Some(sysfs::Node::File(text)) => Ok(text.into_bytes()),
+            Some(sysfs::Node::Dir(_)) => Err(IS_DIR),
+            None => Err(NOT_FOUND),
+        },
+        Backend::Dev => match dev_stat(&rel)? {
+            Stat { is_dir: true, .. } => Err(IS_DIR),
+            _ => Err("Es un dispositivo de bloques; montalo con 'mount'."),
+        },
+    }
+}
+
+/// Create or replace the file at `path`.
+pub fn write(path: &str, data: &[u8]) -> Result<(), &'static str> {
+    // SAFETY: kernel thread; the backends below never come back into the VFS,
+    // so these are the only borrows of the table and the working volume.
+    let mounts = unsafe { table() };
+    let (index, rel) = locate(mounts, &normalize(path));
+    if rel.is_empty() {
+        return Err(IS_DIR);
+    }
+    match &mut mounts[index].backend {
+        Backend::Root => fat_write(unsafe { root_volume() }?, &rel, data),
+        Backend::Fat(fat) => fat_write(fat, &rel, data),
+        Backend::Host => hostfs::write_file(&rel, data),
+        Backend::Sys | Backend::Dev => Err(READ_ONLY),
+    }
+}
+
+pub fn mkdir(path: &str) -> Result<(), &'static str> {
+    // SAFETY: kernel thread; the backends below never come back into the VFS,
+    // so these are the only borrows of the table and the working volume.
+    let mounts = unsafe { table() };
+    let (index, rel) = locate(mounts, &normalize(path));
+    if rel.is_empty() {
+        return Err("Ya existe.");
+    }
+    match &mut mounts[index].backend {
+        Backend::Root => fat_mkdir(unsafe { root_volume() }?, &rel),
+        Backend::Fat(fat) => fat_mkdir(fat, &rel),
+        Backend::Host => hostfs::mkdir(&rel),
+        Backend::Sys | Backend::Dev => Err(READ_ONLY),
+    }
+}
+
+pub fn remove(path: &str) -> Result<(), &'static str> {
+    // SAFETY: kernel thread; the backends below never come back into the VFS,
+    // so these are the only borrows of the table and the working volume.
+    let mounts = unsafe { table() };
+    let (index, rel) = locate(mounts, &normalize(path));
+    if rel.is_empty() {
+        return Err("Es un punto de montaje; usa 'umount'.");
+    }
+    match &mut mounts[index].backend {
+        Backend::Root => fat_remove(unsafe { root_volume() }?, &rel),
+        Backend::Fat(fat) => fat_remove(fat, &rel),
+        Backend::Host => hostfs::remove(&rel),
+        Backend::Sys | Backend::Dev => Err(READ_ONLY),
+    }
+}
+
+/// Copy a file, possibly between filesystems. A folder as `dst` keeps the
+/// name of `src`. Returns the bytes copied.
+pub fn copy(src: &str, dst: &str) -> Result<usize, &'static str> {
+    let data = read(src)?;
+    let target = match stat(dst) {
+        Ok(Stat { is_dir: true, .. }) => join(dst, split_parent(&normalize(src)).1),
+        _ => normalize(dst),
+    };
+    write(&target, &data)?;
+    Ok(data.len())
+}
+
+fn mount_device(source: &str) -> Result<Box<Fat32>, &'static str> {
+    let index = device_index(source)?;
+    let mut fat = Box::new(Fat32::new());
+    fat.mount_uefi_block_device(index)?;
+    let volume = fat.volume_id();
+    // SAFETY: kernel thread; both borrows end with the statement.
+    let root = unsafe { GLOBAL_FAT.get() };
+    let busy = (root.init_status == InitStatus::Success && root.volume_id() == volume)
+        || unsafe { table() }
+            .iter()
+            .any(|m| matches!(&m.backend, Backend::Fat(other) if other.volume_id() == volume));
+    if busy {
+        fat.unmount();
+        return Err("Ese volumen ya esta montado.");
+    }
+    Ok(fat)
+}
+
+/// Attach `source` at `target`: a block device (`/dev/nvme0p1`, a `disks`
+/// index), or `host`, `sys` or `dev`.
+pub fn mount(source: &str, target: &str) -> Result<MountInfo, &'static str> {
+    let target = normalize(target);
+    if target == "/" {
+        return Err("'/' es el volumen de trabajo; cambialo con 'mount <n>'.");
+    }
+    // SAFETY: kernel thread; the borrow ends with the statement.
+    if unsafe { table() }.iter().any(|m| m.path.eq_ignore_ascii_case(&target)) {
+        return Err("Ya hay algo montado ahi.");
+    }
+    if stat(&target).is_ok_and(|s| !s.is_dir) {
+        return Err("El punto de montaje es un archivo.");
+    }
+    let (source, backend) = match source.trim() {
+        "host" | "9p" => (String::from("host"), Backend::Host),
+        "sys" | "sysfs" => (String::from("sys"), Backend::Sys),
+        "dev" | "devfs" => (String::from("dev"), Backend::Dev),
+        device => {
+            let fat = mount_device(device)?;
+            let name = device.strip_prefix("/dev/").unwrap_or(device);
+            (alloc::format!("/dev/{}", name), Backend::Fat(fat))
+        }
+    };
+    let mount = Mount {
+        path: target,
+        source,
+        backend,
+    };
+    let info = mount.info();
+    // SAFETY: kernel thread; `stat` and `mount_device` are done with the table.
+    unsafe { table() }.push(mount);
+    Ok(info)
+}
+
+pub fn umount(target: &str) -> Result<MountInfo, &'static str> {
+    let target = normalize(target);
+    if target == "/" {
+        return Err("No se puede desmontar '/'.");
+    }
+    // SAFETY: kernel thread; nothing below takes the table again, and the
+    // handle table is a cell of its own.
+    let mounts = unsafe { table() };
+    let index = mounts
+        .iter()
+        .position(|m| m.path.eq_ignore_ascii_case(&target))
+        .ok_or("No hay nada montado ahi.")?;
+    let path = mounts[index].path.as_str();
+    if unsafe { handles() }.iter().any(|h| h.mount == path) {
+        return Err("Montaje ocupado: hay archivos abiertos.");
+    }
+    let mut mount = mounts.remove(index);
+    if let Backend::Fat(fat) = &mut mount.backend {
+        fat.unmount();
+    }
+    Ok(mount.info())
+}
+
+// Handles.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpenFlags {
+    pub write: bool,
+    pub create: bool,
+    pub truncate: bool,
+    pub append: bool,
+}
+
+impl OpenFlags {
+    pub const READ: Self = Self {
+        write: false,
+        create: false,
+        truncate: false,
+        append: false,
+    };
+    /// Create or truncate, as `O_WRONLY | O_CREAT | O_TRUNC`.
+    pub const WRITE: Self = Self {
+        write: true,
+        create: true,
+        truncate: true,
+        append: false,
+    };
+    pub const APPEND: Self = Self {
+        write: true,
+        create: true,
+        truncate: false,
+        append: true,
+    };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeekFrom {
+    Start(u64),
+    Current(i64),
+    End(i64),
+}
+
+/// An open file or folder. Ids are never reused, so a stale handle fails
+/// instead of reaching someone else's file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Handle(u64);
+
+struct OpenFile {
+    handle: Handle,
+    path: String,
+    /// Path of the mount it lives on.
+    mount: String,
+    flags: OpenFlags,
+    is_dir: bool,
+    cursor: u64,
+    size: u64,
+    /// Volume id and first cluster of a FAT file read in place.
+    in_place: Option<(u64, u32)>,
+    /// The contents, for anything not read in place.
+    data: Option<Contents>,
+    /// Size of the file as last stored.
+    stored: u64,
+    dirty: bool,
+}
+
+/// File contents in `CHUNK` pieces keyed by index. Pieces never written are
+/// absent and read as zeros.
+#[derive(Default)]
+struct Contents {
+    chunks: BTreeMap<u64, Box<[u8]>>,
+}
+
+impl Contents {
+    fn from_vec(data: &[u8]) -> Result<Self, &'static str> {
+        let mut contents = Self::default();
+        contents.write(0, data)?;
+        Ok(contents)
+    }
+
+    fn read(&self, offset: u64, size: u64, buf: &mut [u8]) -> usize {
+        let len = buf.len().min(size.saturating_sub(offset) as usize);
+        let mut done = 0;
+        while done < len {
+            let pos = offset + done as u64;
+            let skip = (pos % CHUNK as u64) as usize;
+            let take = (CHUNK - skip).min(len - done);
+            match self.chunks.get(&(pos / CHUNK as u64)) {
+                Some(chunk) => buf[done..done + take].copy_from_slice(&chunk[skip..skip + take]),
+                None => buf[done..done + take].fill(0),
+            }
+            done += take;
+        }
+        len
+    }
+
+    fn write(&mut self, offset: u64, buf: &[u8]) -> Result<(), &'static str> {
+        let mut done = 0;
+        while done < buf.len() {
+            let pos = offset + done as u64;
+            let skip = (pos % CHUNK as u64) as usize;
+            let take = (CHUNK - skip).min(buf.len() - done);
+            let index = pos / CHUNK as u64;
+            if !self.chunks.contains_key(&index) {
+                let mut chunk = Vec::new();
+                chunk.try_reserve_exact(CHUNK).map_err(|_| NO_SPACE)?;
+                chunk.resize(CHUNK, 0);
+                self.chunks.insert(index, chunk.into_boxed_slice());
+            }
+            if let Some(chunk) = self.chunks.get_mut(&index) {
+                chunk[skip..skip + take].copy_from_slice(&buf[done..done + take]);
+            }
+            done += take;
+        }
+        Ok(())
+    }
+
+    /// The whole file for the backend, or `NO_SPACE` if it does not fit in
+    /// memory.
+    fn to_vec(&self, size: u64) -> Result<Vec<u8>, &'static str> {
+        let mut out = Vec::new();
+        out.try_reserve_exact(size as usize).map_err(|_| NO_SPACE)?;
+        out.resize(size as usize, 0);
+        self.read(0, size, &mut out);
+        Ok(out)
+    }
+}
+
+static HANDLES: KernelCell<Vec<OpenFile>> = KernelCell::new(Vec::new());
+static NEXT_HANDLE: AtomicU64 = AtomicU64::new(1);
+
+/// The open files.
+///
+/// # Safety
+///
+/// As for `table`: kernel thread only, one borrow in use at a time.
+unsafe fn handles() -> &'static mut Vec<OpenFile> {
+    unsafe { HANDLES.get_mut() }
+}
+
+pub fn open(path: &str, flags: OpenFlags) -> Result<Handle, &'static str> {
+    // SAFETY: kernel thread; the borrow ends with the statement.
+    if unsafe { handles() }.len() >= MAX_HANDLES {
+        return Err("Demasiados archivos abiertos.");
+    }
+    let path = normalize(path);
+    let stat = match stat(&path) {
+        Ok(stat) => stat,
+        Err(NOT_FOUND) if flags.create => {
+            write(&path, &[])?;
+            Stat { is_dir: false, size: 0 }
+        }
+        Err(e) => return Err(e),
+    };
+    if stat.is_dir && flags.write {
+        return Err(IS_DIR);
+    }
+
+    // SAFETY: kernel thread; `mounts` and the volume taken from it are last
+    // used before `read` below takes the table again.
+    let mounts = unsafe { table() };
+    let (index, rel) = locate(mounts, &path);
+    let mount = mounts[index].path.clone();
+    let backend = &mut mounts[index].backend;
+    if flags.write && matches!(backend.kind(), FsKind::Sys | FsKind::Dev) {
+        return Err(READ_ONLY);
+    }
+
+    let mut file = OpenFile {
+        handle: Handle(NEXT_HANDLE.fetch_add(1, Ordering::Relaxed)),
+        path,
+        mount,
+        flags,
+        is_dir: stat.is_dir,
+        cursor: 0,
+        size: stat.size,
+        in_place: None,
+        data: None,
+        stored: stat.size,
+        dirty: false,
+    };
+    if !stat.is_dir {
+        let in_place = match backend {
+            Backend::Root if !flags.write => Some(unsafe { root_volume() }?),
+            Backend::Fat(fat) if !flags.write => Some(&mut **fat),
+            _ => None,
+        };
+        match in_place {
+            Some(fat) => {
+                let (cluster, size) = fat_open(fat, &rel)?;
+                file.in_place = Some((fat.volume_id(), cluster));
+                file.size = size;
+            }
+            None if flags.write && flags.truncate => {
+                file.size = 0;
+                file.dirty = true;
+            }
+            None => {
+                let data = read(&file.path)?;
+                file.size = data.len() as u64;
+                file.data = Some(Contents::from_vec(&data)?);
+            }
+        }
+    }
+    if flags.append {
+        file.cursor = file.size;
+    }
+    let handle = file.handle;
+    // SAFETY: kernel thread; the borrow ends with the statement.
+    unsafe { handles() }.push(file);
+    Ok(handle)
+}
+
+fn with_file<T>(handle: Handle, f: impl FnOnce(&mut OpenFile) -> Result<T, &'static str>) -> Result<T, &'static str> {
+    // SAFETY: kernel thread; `OpenFile` methods reach the mount table and the
+    // volumes, never the handle table.
+    let file = unsafe { handles() }
+        .iter_mut()
+        .find(|f| f.handle == handle)
+        .ok_or("Handle no valido.")?;
+    f(file)
+}
+
+impl OpenFile {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, &'static str> {
+        if self.is_dir {
+            return Err(IS_DIR);
+        }
+        let Some((volume, cluster)) = self.in_place else {
+            return Ok(self.data.as_ref().map_or(0, |data| data.read(offset, self.size, buf)));
+        };
+        // SAFETY: kernel thread; the caller holds only the handle table.
+        let fat = unsafe { volume_at(table(), &self.mount) }?;
+        if fat.volume_id() != volume {
+            return Err("El volumen cambio desde que se abrio el archivo.");
+        }
+        fat.read_file_range(cluster, self.size as usize, offset as usize, buf)
+    }
+
+    fn write_at(&mut self, offset: u64, buf: &[u8]) -> Result<usize, &'static str> {
+        if !self.flags.write {
+            return Err("Abierto solo para lectura.");
+        }
+        let start = if self.flags.append { self.size } else { offset };
+        let end = start.checked_add(buf.len() as u64).ok_or("Posicion no valida.")?;
+        if end > self.size {
+            if end > MAX_FILE_SIZE {
+                return Err(TOO_BIG);
+            }
+            if end > self.stored.saturating_add(self.free_space()) {
+                return Err(NO_SPACE);
+            }
+        }
+        self.data.get_or_insert_with(Contents::default).write(start, buf)?;
+        self.size = self.size.max(end);
+        self.dirty = true;
+        Ok(buf.len())
+    }
+
+    /// Bytes free on the file's FAT volume; the host export does not say,
+    /// so only `MAX_FILE_SIZE` holds there.
+    fn free_space(&self) -> u64 {
+        // SAFETY: kernel thread; the caller holds only the handle table.
+        match unsafe { volume_at(table(), &self.mount) } {
+            Ok(fat) => fat.free_bytes().unwrap_or(u64::MAX),
+            Err(_) => u64::MAX,
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), &'static str> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let data = match self.data.as_ref() {
+            Some(data) => data.to_vec(self.size)?,
+            None => Vec::new(),
+        };
+        write(&self.path, &data)?;
+        self.stored = self.size;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+impl Handle {
+    /// Number the Linux shim keeps in its descriptor slot.
+    pub const fn id(self) -> u64 {
+        self.0
+    }
+
+    pub const fn from_id(id: u64) -> Self {
+        Self(id)
+    }
+
+    pub fn read(self, buf: &mut [u8]) -> Result<usize, &'static str> {
+        with_file(self, |f| {
+            let len = f.read_at(f.cursor, buf)?;
+            f.cursor += len as u64;
+            Ok(len)
+        })
+    }
+
+    pub fn read_at(self, offset: u64, buf: &mut [u8]) -> Result<usize, &'static str> {
+        with_file(self, |f| f.read_at(offset, buf))
+    }
+
+    pub fn write(self, buf: &[u8]) -> Result<usize, &'static str> {
+        with_file(self, |f| {
+            let len = f.write_at(f.cursor, buf)?;
+            f.cursor = if f.flags.append { f.size } else { f.cursor + len as u64 };
+            Ok(len)
+        })
+    }
+
+    pub fn write_at(self, offset: u64, buf: &[u8]) -> Result<usize, &'static str> {
+        with_file(self, |f| f.write_at(offset, buf))
+    }
+
+    pub fn seek(self, pos: SeekFrom) -> Result<u64, &'static str> {
+        with_file(self, |f| {
+            let target = match pos {
+                SeekFrom::Start(offset) => Some(offset),
+                SeekFrom::Current(delta) => f.cursor.checked_add_signed(delta),
+                SeekFrom::End(delta) => f.size.checked_add_signed(delta),
+            };
+            f.cursor = target.ok_or("Posicion no valida.")?;
+            Ok(f.cursor)
+        })
+    }
+
+    pub fn stat(self) -> Result<Stat, &'static str> {
+        with_file(self, |f| {
+            Ok(Stat {
+                is_dir: f.is_dir,
+                size: f.size,
+            })
+        })
+    }
+
+    pub fn path(self) -> Result<String, &'static str> {
+        with_file(self, |f| Ok(f.path.clone()))
+    }
+
+    /// Store what was written so far.
+    pub fn flush(self) -> Result<(), &'static str> {
+        with_file(self, OpenFile::flush)
+    }
+
+    /// Store what was written and release the handle, even when storing fails.
+    pub fn close(self) -> Result<(), &'static str> {
+        // SAFETY: kernel thread; the file is out of the table before `flush`.
+        let open = unsafe { handles() };
+        let index = open.iter().position(|f| f.handle == self).ok_or("Handle no valido.")?;
+        let mut file = open.remove(index);
+        file.flush()
+    }
+}
+
+// Terminal.
+
+pub fn mounts_lines() -> Vec<String> {
+    let mut out = alloc::vec![String::from("Montajes:")];
+    for mount in mounts() {
+        out.push(alloc::format!(
+            "  {:<12} {:<6} {}",
+            mount.path,
+            mount.kind.as_str(),
+            mount.source
+        ));
+    }
+    out
+}
+
+pub fn ls_lines(path: &str) -> Vec<String> {
+    match read_dir(path) {
+        Ok(entries) => {
+            let mut out = alloc::vec![alloc::format!("{}:", normalize(path))];
+            for entry in entries.iter() {
+                out.push(alloc::format!(
+                    "  [{}] {} ({} bytes)",
+                    if entry.is_dir { "DIR " } else { "FILE" },
+                    entry.name,
+                    entry.size
+                ));
+            }
+            if entries.is_empty() {
+                out.push(String::from("  (vacio)"));
+            }
+            out
+        }
+        Err(e) => alloc::vec![alloc::format!("ls: {}: {}", normalize(path), e)],
+    }
+}
+
+pub fn cat_lines(path: &str) -> Vec<String> {
+    match read(path) {
+        Ok(data) => {
+            let shown = &data[..data.len().min(CAT_LIMIT)];
+            let mut out: Vec<String> = match core::str::from_utf8(shown) {
+                Ok(text) => text.lines().map(String::from).collect(),
+                Err(_) => alloc::vec![String::from("<binary>")],
+            };
+            if data.len() > CAT_LIMIT {
+                out.push(String::from("[output truncated]"));
+            }
+            out
+        }
+        Err(e) => alloc::vec![alloc::format!("cat: {}: {}", normalize(path), e)],
+    }
+}
+
+/// Terminal commands on the mount table (`mounts`, `mount <origen> <dir>`,
+/// `umount <dir>`) and `ls`, `cat`, `mkdir`, `rm` and `cp` on paths below a
+/// mount. `None` leaves the command to the working volume, including the
+/// older `mount <n>` and a bare `umount`.
+pub fn command_lines(verb: &str, args: &str) -> Option<Vec<String>> {
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let result = |r: Result<String, &'static str>| match r {
+        Ok(line) => alloc::vec![line],
+        Err(e) => alloc::vec![alloc::format!("{}: {}", verb, e)],
+    };
+    let lines = match (verb, parts.as_slice()) {
+        ("mounts", []) | ("mount", []) => mounts_lines(),
+        ("mount", [source, target]) => result(
+            mount(source, target).map(|m| alloc::format!("{} montado en {} ({})", m.source, m.path, m.kind.as_str())),
+        ),
+        ("umount" | "unmount", [target]) => {
+            result(umount(target).map(|m| alloc::format!("{} desmontado de {}", m.source, m.path)))
+        }
+        ("ls", [path]) if is_mounted_path(path) => ls_lines(path),
+        ("cat", [path]) if is_mounted_path(path) => cat_lines(path),
+        ("mkdir", [path]) if is_mounted_path(path) => {
+            result(mkdir(path).map(|_| alloc::format!("Creado {}", normalize(path))))
+        }
+        ("rm", [path]) if is_mounted_path(path) => {
+            result(remove(path).map(|_| alloc::format!("Borrado {}", normalize(path))))
+        }
+        ("cp", [src, dst]) if is_mounted_path(src) || is_mounted_path(dst) => {
+            if !src.starts_with('/') || !dst.starts_with('/') {
+                alloc::vec![String::from(
+                    "cp: entre montajes usa rutas absolutas (/data/A.TXT /B.TXT)."
+                )]
+            } else {
+                result(copy(src, dst).map(|len| alloc::format!("{} -> {} ({} bytes)", src, dst, len)))
+            }
+        }
+        _ => return None,
+    };
+    Some(lines)
+}
+
+crate::selftest::kernel_tests! {
+    "vfs";
+
+    fn normalizes_paths() {
+        crate::selftest::ensure_eq(normalize("a/./b/../c/"), String::from("/a/c"), "relativa")?;
+        crate::selftest::ensure_eq(normalize("/../"), String::from("/"), "raiz")?;
+        crate::selftest::ensure_eq(normalize("\\DOCS\\A.TXT"), String::from("/DOCS/A.TXT"), "barras invertidas")?;
+        crate::selftest::ensure_eq(relative("/HOST/x", "/host"), Some("x"), "sin mayusculas")?;
+        crate::selftest::ensure_eq(relative("/hostile", "/host"), None, "prefijo de nombre")?;
+        crate::selftest::ensure_eq(join("/data/", "A.TXT"), String::from("/data/A.TXT"), "union")
+    }
+
+    fn names_block_devices() {
+        let names = device_names(&[(false, false), (false, true), (false, true), (true, true), (false, false)]);
+        let expected = ["nvme0", "nvme0p1", "nvme0p2", "usb0p1", "nvme1"];
+        crate::selftest::ensure_eq(names.iter().map(String::as_str).collect::<Vec<_>>(), expected.to_vec(), "nombres")?;
+        crate::selftest::ensure_eq(device_index("/dev/disk3"), Ok(3), "por indice")
+    }
+
+    fn holds_sparse_contents() {
+        let far = 1u64 << 40;
+        let mut contents = Contents::from_vec(b"abc")?;
+        contents.write(far, b"z")?;
+        crate::selftest::ensure_eq(contents.chunks.len(), 2, "sin rellenar el hueco")?;
+        let mut buf = [9u8; 4];
+        crate::selftest::ensure_eq(contents.read(far - 3, far + 1, &mut buf), 4, "lectura del hueco")?;
+        crate::selftest::ensure_eq(&buf, b"\0\0\0z", "ceros")?;
+        crate::selftest::ensure_eq(contents.to_vec(5), Ok(b"abc\0\0".to_vec()), "volcado")
+    }
+
+    fn mounts_resolve_and_handles_read() {
+        fn check() -> crate::selftest::TestResult {
+            crate::selftest::ensure_eq(mount_kind("/vfs-test/sys/devices"), Some(FsKind::Sys), "prefijo mas largo")?;
+            crate::selftest::ensure_eq(mount_kind("/vfs-test"), None, "volumen de trabajo")?;
+            let file = "/vfs-test/sys/devices/vfs-test/disk0/size";
+            crate::selftest::ensure_eq(stat(file), Ok(Stat { is_dir: false, size: 3 }), "stat")?;
+            let names: Vec<String> = read_dir("/vfs-test/sys/devices/vfs-test")?.into_iter().map(|e| e.name).collect();
+            crate::selftest::ensure(names.iter().any(|n| n == "disk0"), "listado")?;
+            crate::selftest::ensure(write(file, b"1").is_err(), "solo lectura")?;
+
+            let handle = open(file, OpenFlags::READ)?;
+            let mut buf = [0u8; 8];
+            let first = handle.read(&mut buf);
+            let second = handle.seek(SeekFrom::End(-2)).and_then(|_| handle.read(&mut buf[3..]));
+            let busy = umount("/vfs-test/sys").is_err();
+            handle.close()?;
+            crate::selftest::ensure_eq(first, Ok(3), "lectura")?;
+            crate::selftest::ensure_eq(second, Ok(2), "tras seek")?;
+            crate::selftest::ensure_eq(&buf[..5], b"51212".as_slice(), "contenido")?;
+            crate::selftest::ensure(busy, "montaje ocupado")?;
+            crate::selftest::ensure(handle.read(&mut buf).is_err(), "handle cerrado")
+        }
+
+        let top = crate::device::add(None, "vfs-test", None, None);
+        let child = crate::device::add(Some(top), "disk0", Some("vfs-test"), Some("prueba"));
+        crate::device::set_attr(child, "size", "512");
+        let result = mount("sys", "/vfs-test/sys").map_err(String::from).and_then(|_| check());
+        let _ = umount("/vfs-test/sys");
+        crate::device::remove(top);
+        result
+    }
+}
@@ -0,0 +1,207 @@
+//! Intent journal for FAT32 metadata.
+//!
+//! Growing a file or creating an entry touches several sectors (both FAT
+//! copies, the directory entry, a new directory cluster); power lost between
+//! them leaves chains that point nowhere or clusters nobody owns. While a
+//! `Fat32` mutator runs, those metadata sectors are staged in memory; when
+//! it finishes they are written here first, then to their real place, and
+//! the journal is marked clean. Mounting a volume whose journal is still
+//! committed writes the recorded sectors again, so the update ends up whole
+//! or, if the journal itself was cut short, not at all. File data is written
+//! straight away, before its metadata.
+//!
+//! The journal is `\REDUXOS\FSJRNL.DAT`, created on the first write to a
+//! volume and never moved, so its sectors are written directly:
+//!
+//! - sector 0: header (magic, sequence, state, record count, CRC32)
+//! - `descriptor_sectors(count)` sectors of target LBAs (u64 each)
+//! - `count` sectors with the new contents, in the same order
+//!
+//! exFAT volumes are written without the journal.
+
+use alloc::vec::Vec;
+
+pub const DIR: &str = "REDUXOS";
+pub const FILE: &str = "FSJRNL.DAT";
+/// Size of the journal file: 1 MiB.
+pub const SECTORS: usize = 2048;
+pub const SECTOR_SIZE: usize = 512;
+const MAGIC: &[u8; 4] = b"RXJL";
+const VERSION: u8 = 1;
+const LBAS_PER_SECTOR: usize = SECTOR_SIZE / 8;
+
+pub type Sector = [u8; SECTOR_SIZE];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum State {
+    Clean,
+    /// Records complete; they may not have reached their place yet.
+    Committed,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Header {
+    pub seq: u64,
+    pub state: State,
+    pub count: u32,
+    /// Over the descriptor and data sectors.
+    pub crc: u32,
+}
+
+impl Header {
+    pub fn clean(seq: u64) -> Self {
+        Self {
+            seq,
+            state: State::Clean,
+            count: 0,
+            crc: 0,
+        }
+    }
+
+    pub fn encode(&self) -> Sector {
+        let mut out = [0u8; SECTOR_SIZE];
+        out[..4].copy_from_slice(MAGIC);
+        out[4] = VERSION;
+        out[5] = match self.state {
+            State::Clean => 0,
+            State::Committed => 1,
+        };
+        out[8..16].copy_from_slice(&self.seq.to_le_bytes());
+        out[16..20].copy_from_slice(&self.count.to_le_bytes());
+        out[20..24].copy_from_slice(&self.crc.to_le_bytes());
+        let check = crate::compress::crc32(&out[..24]);
+        out[24..28].copy_from_slice(&check.to_le_bytes());
+        out
+    }
+
+    /// `None` for a sector that is not a valid header (a fresh, zeroed
+    /// journal included).
+    pub fn decode(raw: &[u8]) -> Option<Self> {
+        if raw.len() < 28 || &raw[..4] != MAGIC || raw[4] != VERSION {
+            return None;
+        }
+        let u32_at = |at: usize| u32::from_le_bytes([raw[at], raw[at + 1], raw[at + 2], raw[at + 3]]);
+        if crate::compress::crc32(&raw[..24]) != u32_at(24) {
+            return None;
+        }
+        let state = match raw[5] {
+            0 => State::Clean,
+            1 => State::Committed,
+            _ => return None,
+        };
+        let mut seq = [0u8; 8];
+        seq.copy_from_slice(&raw[8..16]);
+        Some(Self {
+            seq: u64::from_le_bytes(seq),
+            state,
+            count: u32_at(16),
+            crc: u32_at(20),
+        })
+    }
+}
+
+pub fn descriptor_sectors(count: usize) -> usize {
+    count.div_ceil(LBAS_PER_SECTOR)
+}
+
+/// Sectors a transaction of `count` records takes after the header.
+pub fn body_sectors(count: usize) -> usize {
+    descriptor_sectors(count) + count
+}
+
+/// Most records a journal of `sectors` sectors holds at once.
+pub fn capacity(sectors: usize) -> usize {
+    let room = sectors.saturating_sub(1);
+    // Each full descriptor sector covers LBAS_PER_SECTOR records.
+    let mut count = room * LBAS_PER_SECTOR / (LBAS_PER_SECTOR + 1);
+    while count > 0 && body_sectors(count) > room {
+        count -= 1;
+    }
+    count
+}
+
+/// Committed header and body sectors for `writes` (target LBA, contents).
+pub fn encode(seq: u64, writes: &[(u64, Sector)]) -> (Header, Vec<Sector>) {
+    let mut body = Vec::with_capacity(body_sectors(writes.len()));
+    for chunk in writes.chunks(LBAS_PER_SECTOR) {
+        let mut sector = [0u8; SECTOR_SIZE];
+        for (i, (lba, _)) in chunk.iter().enumerate() {
+            sector[i * 8..i * 8 + 8].copy_from_slice(&lba.to_le_bytes());
+        }
+        body.push(sector);
+    }
+    body.extend(writes.iter().map(|(_, data)| *data));
+    let header = Header {
+        seq,
+        state: State::Committed,
+        count: writes.len() as u32,
+        crc: body_crc(&body),
+    };
+    (header, body)
+}
+
+fn body_crc(body: &[Sector]) -> u32 {
+    let mut flat = Vec::with_capacity(body.len() * SECTOR_SIZE);
+    for sector in body.iter() {
+        flat.extend_from_slice(sector);
+    }
+    crate::compress::crc32(&flat)
+}
+
+/// Records of a committed transaction, or `None` when `body` does not match
+/// `header` (the journal write itself was interrupted).
+pub fn decode(header: &Header, body: &[Sector]) -> Option<Vec<(u64, Sector)>> {
+    let count = header.count as usize;
+    if header.state != State::Committed || body.len() != body_sectors(count) || body_crc(body) != header.crc {
+        return None;
+    }
+    let descriptors = descriptor_sectors(count);
+    let mut out = Vec::with_capacity(count);
+    for i in 0..count {
+        let sector = &body[i / LBAS_PER_SECTOR];
+        let at = (i % LBAS_PER_SECTOR) * 8;
+        let mut lba = [0u8; 8];
+        lba.copy_from_slice(&sector[at..at + 8]);
+        out.push((u64::from_le_bytes(lba), body[descriptors + i]));
+    }
+    Some(out)
+}
+
+crate::selftest::kernel_tests! {
+    "fsjournal";
+
+    fn header_round_trip() {
+        let header = Header {
+            seq: 41,
+            state: State::Committed,
+            count: 3,
+            crc: 0xDEAD_BEEF,
+        };
+        let raw = header.encode();
+        crate::selftest::ensure_eq(Header::decode(&raw), Some(header), "cabecera")?;
+        crate::selftest::ensure(Header::decode(&[0u8; SECTOR_SIZE]).is_none(), "diario vacio")?;
+        let mut damaged = raw;
+        damaged[16] ^= 1;
+        crate::selftest::ensure(Header::decode(&damaged).is_none(), "cabecera danada")
+    }
+
+    fn records_round_trip() {
+        let writes: Vec<(u64, Sector)> = (0..70u64).map(|i| (1000 + i * 3, [i as u8; SECTOR_SIZE])).collect();
+        let (header, body) = encode(7, &writes);
+        crate::selftest::ensure_eq(body.len(), 2 + 70, "sectores")?;
+        let decoded = decode(&header, &body).ok_or("sin registros")?;
+        crate::selftest::ensure_eq(decoded.len(), 70, "registros")?;
+        crate::selftest::ensure_eq(decoded[69].0, 1000 + 69 * 3, "lba")?;
+        crate::selftest::ensure_eq(decoded[69].1[0], 69, "datos")?;
+        let mut torn = body.clone();
+        torn[5][0] ^= 0xFF;
+        crate::selftest::ensure(decode(&header, &torn).is_none(), "escritura cortada")
+    }
+
+    fn capacity_fits() {
+        let cap = capacity(SECTORS);
+        crate::selftest::ensure(body_sectors(cap) < SECTORS, "cabe")?;
+        crate::selftest::ensure(1 + body_sectors(cap + 1) > SECTORS, "maximo")?;
+        crate::selftest::ensure_eq(capacity(1), 0, "sin sitio")
+    }
+}
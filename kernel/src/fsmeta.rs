@@ -0,0 +1,826 @@
+//! Symbolic links, extended attributes and ownership on top of FAT32/exFAT.
+//!
+//! Neither filesystem can store them, so each volume keeps them in a
+//! sidecar, `\REDUXOS\FSMETA.DAT`, keyed by the folder cluster and the
+//! entry name. A symlink is an empty placeholder file (so listings, renames
+//! and deletes work as for any file) plus its target in the sidecar;
+//! attributes (`user.*`, `security.*`, `trusted.*`, `system.*`) can hang off
+//! any entry, and so can the owner, group and mode `crate::perm` checks.
+//! `crate::fswatch` keeps the sidecar in step: a rename or move
+//! carries the metadata along, a delete drops it and a write over a link
+//! turns it back into a plain file.
+//!
+//! `resolve` walks a path following links (at most `SYMLINK_MAX_HOPS`);
+//! the terminal uses it for `cd`, `cat`, intermediate folders and programs
+//! run through a linked `bin` folder.
+//!
+//! The sidecar is read and written as root: it belongs to the kernel, not to
+//! whoever triggered the change.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::fat32::Fat32;
+use crate::fs::{DirEntry, FileType};
+use crate::perm::{Cred, Perm};
+use crate::spinlock::SpinLock;
+
+const DIR: &str = "REDUXOS";
+const META_FILE: &str = "FSMETA.DAT";
+const MAGIC: &[u8; 4] = b"RXMD";
+/// Version 2 added ownership; version 1 files still load.
+const VERSION: u8 = 2;
+pub const SYMLINK_MAX_HOPS: usize = 8;
+pub const TARGET_MAX_BYTES: usize = 1024;
+pub const XATTR_KEY_MAX_BYTES: usize = 64;
+pub const XATTR_VALUE_MAX_BYTES: usize = 1024;
+const XATTRS_PER_ENTRY: usize = 16;
+/// Volumes whose sidecar stays loaded.
+const CACHED_VOLUMES: usize = 4;
+const XATTR_NAMESPACES: [&str; 4] = ["user.", "security.", "trusted.", "system."];
+
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+struct Node {
+    /// Name as given when the entry got its metadata.
+    name: String,
+    link: Option<String>,
+    xattrs: BTreeMap<String, Vec<u8>>,
+    perm: Option<Perm>,
+}
+
+impl Node {
+    fn is_empty(&self) -> bool {
+        self.link.is_none() && self.xattrs.is_empty() && self.perm.is_none()
+    }
+}
+
+/// Folder cluster and lowercase entry name.
+type Key = (u32, String);
+
+fn key(dir: u32, name: &str) -> Key {
+    (dir, name.to_ascii_lowercase())
+}
+
+/// Metadata of one volume.
+#[derive(Default)]
+struct Table {
+    volume: u64,
+    nodes: BTreeMap<Key, Node>,
+    /// `MovedFrom` waiting for its `MovedTo`.
+    moving: Option<(u32, Key)>,
+    /// Changed since the sidecar was written.
+    dirty: bool,
+}
+
+impl Table {
+    fn new(volume: u64) -> Self {
+        Self {
+            volume,
+            ..Self::default()
+        }
+    }
+
+    fn link(&self, dir: u32, name: &str) -> Option<&String> {
+        self.nodes.get(&key(dir, name)).and_then(|n| n.link.as_ref())
+    }
+
+    fn node_mut(&mut self, dir: u32, name: &str) -> &mut Node {
+        self.nodes.entry(key(dir, name)).or_insert_with(|| Node {
+            name: String::from(name),
+            ..Node::default()
+        })
+    }
+
+    fn prune(&mut self, dir: u32, name: &str) {
+        let k = key(dir, name);
+        if self.nodes.get(&k).is_some_and(Node::is_empty) {
+            self.nodes.remove(&k);
+        }
+    }
+
+    /// Follow a change reported by `crate::fswatch`.
+    fn apply(&mut self, event: &crate::fswatch::Event) {
+        use crate::fswatch::Change;
+        match event.change {
+            Change::Removed if event.name.is_empty() => {
+                let before = self.nodes.len();
+                self.nodes.retain(|(dir, _), _| *dir != event.dir);
+                self.dirty |= self.nodes.len() != before;
+            }
+            Change::Removed => {
+                self.dirty |= self.nodes.remove(&key(event.dir, &event.name)).is_some();
+            }
+            Change::Written => {
+                if let Some(node) = self.nodes.get_mut(&key(event.dir, &event.name)) {
+                    if node.link.take().is_some() {
+                        self.dirty = true;
+                        self.prune(event.dir, &event.name);
+                    }
+                }
+            }
+            Change::MovedFrom => self.moving = Some((event.cookie, key(event.dir, &event.name))),
+            Change::MovedTo => {
+                let Some((cookie, from)) = self.moving.take() else {
+                    return;
+                };
+                if cookie != event.cookie {
+                    return;
+                }
+                let to = key(event.dir, &event.name);
+                // Whatever the move replaced is gone.
+                let replaced = self.nodes.remove(&to).is_some();
+                if let Some(mut node) = self.nodes.remove(&from) {
+                    node.name = event.name.clone();
+                    self.nodes.insert(to, node);
+                    self.dirty = true;
+                }
+                self.dirty |= replaced;
+            }
+            Change::Created | Change::Overflow => {}
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.extend_from_slice(&(self.nodes.len() as u32).to_le_bytes());
+        for ((dir, _), node) in self.nodes.iter() {
+            out.extend_from_slice(&dir.to_le_bytes());
+            push_str16(&mut out, &node.name);
+            push_str16(&mut out, node.link.as_deref().unwrap_or(""));
+            out.push(node.xattrs.len() as u8);
+            for (name, value) in node.xattrs.iter() {
+                out.push(name.len() as u8);
+                out.extend_from_slice(name.as_bytes());
+                out.extend_from_slice(&(value.len() as u16).to_le_bytes());
+                out.extend_from_slice(value);
+            }
+            match node.perm {
+                Some(perm) => {
+                    out.push(1);
+                    out.extend_from_slice(&perm.uid.to_le_bytes());
+                    out.extend_from_slice(&perm.gid.to_le_bytes());
+                    out.extend_from_slice(&perm.mode.to_le_bytes());
+                }
+                None => out.push(0),
+            }
+        }
+        let crc = crate::compress::crc32(&out);
+        out.extend_from_slice(&crc.to_le_bytes());
+        out
+    }
+
+    fn decode(volume: u64, raw: &[u8]) -> Result<Self, &'static str> {
+        if raw.len() < 4 + 1 + 4 + 4 || &raw[..4] != MAGIC {
+            return Err("fsmeta: archivo no reconocido");
+        }
+        let (body, crc) = raw.split_at(raw.len() - 4);
+        if crate::compress::crc32(body) != u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]]) {
+            return Err("fsmeta: archivo corrupto (CRC)");
+        }
+        let version = body[4];
+        if version != 1 && version != VERSION {
+            return Err("fsmeta: version no soportada");
+        }
+        let mut reader = Reader { data: body, pos: 5 };
+        let count = reader.u32().ok_or("fsmeta: truncado")?;
+        let mut table = Self::new(volume);
+        for _ in 0..count {
+            let dir = reader.u32().ok_or("fsmeta: truncado")?;
+            let name = reader.str16().ok_or("fsmeta: truncado")?;
+            let link = reader.str16().ok_or("fsmeta: truncado")?;
+            let mut node = Node {
+                name,
+                link: if link.is_empty() { None } else { Some(link) },
+                xattrs: BTreeMap::new(),
+                perm: None,
+            };
+            for _ in 0..reader.u8().ok_or("fsmeta: truncado")? {
+                let len = reader.u8().ok_or("fsmeta: truncado")? as usize;
+                let attr = core::str::from_utf8(reader.bytes(len).ok_or("fsmeta: truncado")?)
+                    .map_err(|_| "fsmeta: atributo invalido")?;
+                let attr = String::from(attr);
+                let len = reader.u16().ok_or("fsmeta: truncado")? as usize;
+                let value = reader.bytes(len).ok_or("fsmeta: truncado")?.to_vec();
+                node.xattrs.insert(attr, value);
+            }
+            if version >= 2 && reader.u8().ok_or("fsmeta: truncado")? != 0 {
+                node.perm = Some(Perm {
+                    uid: reader.u32().ok_or("fsmeta: truncado")?,
+                    gid: reader.u32().ok_or("fsmeta: truncado")?,
+                    mode: reader.u16().ok_or("fsmeta: truncado")?,
+                });
+            }
+            table.nodes.insert(key(dir, &node.name), node);
+        }
+        Ok(table)
+    }
+}
+
+fn push_str16(out: &mut Vec<u8>, text: &str) {
+    out.extend_from_slice(&(text.len() as u16).to_le_bytes());
+    out.extend_from_slice(text.as_bytes());
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let out = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(out)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn str16(&mut self) -> Option<String> {
+        let len = self.u16()? as usize;
+        core::str::from_utf8(self.bytes(len)?).ok().map(String::from)
+    }
+}
+
+/// Loaded sidecars, most recently used last.
+static TABLES: SpinLock<Vec<Table>> = SpinLock::new(Vec::new());
+
+pub fn init() {
+    crate::fswatch::subscribe(on_fs_event);
+}
+
+fn on_fs_event(event: &crate::fswatch::Event) {
+    let mut tables = TABLES.lock();
+    if let Some(table) = tables.iter_mut().find(|t| t.volume == event.volume) {
+        table.apply(event);
+    }
+}
+
+fn meta_dir(fat: &mut Fat32) -> Option<u32> {
+    let root = fat.root_cluster;
+    let entries = fat.read_dir_entries(root).ok()?;
+    entries
+        .iter()
+        .find(|e| e.valid && e.file_type == FileType::Directory && e.matches_name(DIR))
+        .map(|e| if e.cluster < 2 { root } else { e.cluster })
+}
+
+/// Run `f` on the metadata of `fat`'s volume, loading its sidecar first if
+/// needed. Volumes without one start empty.
+fn with_table<R>(fat: &mut Fat32, f: impl FnOnce(&mut Table) -> R) -> R {
+    let volume = fat.volume_id();
+    let loaded = TABLES.lock().iter().any(|t| t.volume == volume);
+    if !loaded {
+        let _root = crate::perm::act_as(Cred::ROOT);
+        let table = meta_dir(fat)
+            .and_then(|dir| fat.read_file_in_dir(dir, META_FILE).ok())
+            .map(|raw| {
+                Table::decode(volume, raw.as_slice()).unwrap_or_else(|e| {
+                    crate::klog::log(crate::klog::Level::Warning, e);
+                    Table::new(volume)
+                })
+            })
+            .unwrap_or_else(|| Table::new(volume));
+        let mut tables = TABLES.lock();
+        if !tables.iter().any(|t| t.volume == volume) {
+            // Unsaved changes of the oldest volume are lost; they only come
+            // from renames seen while it was not mounted.
+            if tables.len() >= CACHED_VOLUMES {
+                tables.remove(0);
+            }
+            tables.push(table);
+        }
+    }
+    let mut tables = TABLES.lock();
+    let index = tables.iter().position(|t| t.volume == volume).unwrap_or(0);
+    let table = tables.remove(index);
+    tables.push(table);
+    let last = tables.len() - 1;
+    f(&mut tables[last])
+}
+
+/// Write `fat`'s sidecar if anything changed.
+fn save(fat: &mut Fat32) -> Result<(), &'static str> {
+    let volume = fat.volume_id();
+    let encoded = {
+        let mut tables = TABLES.lock();
+        let Some(table) = tables.iter_mut().find(|t| t.volume == volume && t.dirty) else {
+            return Ok(());
+        };
+        table.dirty = false;
+        table.encode()
+    };
+    let root = fat.root_cluster;
+    let _root = crate::perm::act_as(Cred::ROOT);
+    let result = fat
+        .ensure_subdirectory(root, DIR)
+        .and_then(|dir| fat.write_text_file_in_dir(dir, META_FILE, encoded.as_slice()));
+    if result.is_err() {
+        if let Some(table) = TABLES.lock().iter_mut().find(|t| t.volume == volume) {
+            table.dirty = true;
+        }
+    }
+    result
+}
+
+/// Write the sidecar of the mounted volume when renames or deletes changed
+/// it. Called from the compositor's background loop, since the fswatch
+/// callback cannot write.
+pub fn flush_pending() {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    if fat.init_status != crate::fat32::InitStatus::Success {
+        return;
+    }
+    let volume = fat.volume_id();
+    if TABLES.lock().iter().any(|t| t.volume == volume && t.dirty) {
+        let _ = save(fat);
+    }
+}
+
+/// Folder clusters as `crate::fswatch` reports them: below 2 is the root.
+fn norm(fat: &Fat32, dir: u32) -> u32 {
+    if dir < 2 {
+        fat.root_cluster
+    } else {
+        dir
+    }
+}
+
+fn find_entry(fat: &mut Fat32, dir: u32, name: &str) -> Option<DirEntry> {
+    let entries = fat.read_dir_entries(dir).ok()?;
+    entries
+        .into_iter()
+        .find(|e| e.valid && (e.matches_name(name) || e.full_name().eq_ignore_ascii_case(name)))
+}
+
+fn parent_of(fat: &mut Fat32, dir: u32) -> u32 {
+    let root = fat.root_cluster;
+    if dir == root {
+        return root;
+    }
+    match find_entry(fat, dir, "..") {
+        Some(e) if e.cluster >= 2 => e.cluster,
+        _ => root,
+    }
+}
+
+/// Where a path ends up once its links are followed.
+pub struct Resolved {
+    /// Folder holding the last component.
+    pub dir: u32,
+    /// Last component; empty when the path names `dir` itself.
+    pub name: String,
+    /// `None` when the last component does not exist (yet).
+    pub entry: Option<DirEntry>,
+    /// The same place without links, from the root when it starts with '/'.
+    pub path: String,
+}
+
+impl Resolved {
+    /// Cluster of the folder the path names, if it names one.
+    pub fn dir_cluster(&self, root: u32) -> Option<u32> {
+        match self.entry {
+            _ if self.name.is_empty() => Some(self.dir),
+            Some(e) if e.file_type == FileType::Directory => Some(if e.cluster < 2 { root } else { e.cluster }),
+            _ => None,
+        }
+    }
+}
+
+/// Walk `path` from `base` (from the root when it starts with '/'),
+/// following links in every component and, with `follow_last`, in the last
+/// one too.
+pub fn resolve(fat: &mut Fat32, base: u32, path: &str, follow_last: bool) -> Result<Resolved, &'static str> {
+    let root = fat.root_cluster;
+    let path = path.trim().replace('\\', "/");
+    let mut dir = if path.starts_with('/') || base < 2 { root } else { base };
+    let mut absolute = path.starts_with('/');
+    let mut shown: Vec<String> = Vec::new();
+    // Components still to walk, last one first.
+    let mut pending: Vec<String> = path
+        .split('/')
+        .filter(|p| !p.trim().is_empty() && p.trim() != ".")
+        .map(|p| String::from(p.trim()))
+        .rev()
+        .collect();
+    let mut hops = 0usize;
+    let text = |absolute: bool, parts: &[String]| {
+        let joined = parts.join("/");
+        if absolute {
+            alloc::format!("/{}", joined)
+        } else {
+            joined
+        }
+    };
+
+    while let Some(part) = pending.pop() {
+        if part == ".." {
+            dir = parent_of(fat, dir);
+            if shown.pop().is_none() && !absolute {
+                shown.push(String::from(".."));
+            }
+            continue;
+        }
+        let last = pending.is_empty();
+        let Some(entry) = find_entry(fat, dir, &part) else {
+            if last {
+                shown.push(part.clone());
+                return Ok(Resolved {
+                    dir,
+                    name: part,
+                    entry: None,
+                    path: text(absolute, &shown),
+                });
+            }
+            return Err("ruta no encontrada");
+        };
+        if !last || follow_last {
+            let target = with_table(fat, |t| t.link(dir, &part).cloned());
+            if let Some(target) = target {
+                hops += 1;
+                if hops > SYMLINK_MAX_HOPS {
+                    return Err("demasiados enlaces simbolicos");
+                }
+                if target.starts_with('/') {
+                    dir = root;
+                    absolute = true;
+                    shown.clear();
+                }
+                pending.extend(
+                    target
+                        .split('/')
+                        .filter(|p| !p.trim().is_empty() && p.trim() != ".")
+                        .map(|p| String::from(p.trim()))
+                        .rev(),
+                );
+                continue;
+            }
+        }
+        shown.push(entry.full_name());
+        if last {
+            return Ok(Resolved {
+                dir,
+                name: entry.full_name(),
+                entry: Some(entry),
+                path: text(absolute, &shown),
+            });
+        }
+        if entry.file_type != FileType::Directory {
+            return Err("no es un directorio");
+        }
+        dir = if entry.cluster < 2 { root } else { entry.cluster };
+    }
+    Ok(Resolved {
+        dir,
+        name: String::new(),
+        entry: None,
+        path: text(absolute, &shown),
+    })
+}
+
+/// Cluster of the folder `path` names from `base`, through links.
+pub fn resolve_dir(fat: &mut Fat32, base: u32, path: &str) -> Result<u32, &'static str> {
+    let root = fat.root_cluster;
+    resolve(fat, base, path, true)?
+        .dir_cluster(root)
+        .ok_or("no es un directorio")
+}
+
+/// `entry` of folder `dir`, or what it points to when it is a link.
+pub fn follow(fat: &mut Fat32, dir: u32, entry: &DirEntry) -> Result<DirEntry, &'static str> {
+    let dir = norm(fat, dir);
+    let name = entry.full_name();
+    if readlink(fat, dir, &name).is_none() {
+        return Ok(*entry);
+    }
+    resolve(fat, dir, &name, true)?.entry.ok_or("enlace roto")
+}
+
+pub fn readlink(fat: &mut Fat32, dir: u32, name: &str) -> Option<String> {
+    let dir = norm(fat, dir);
+    with_table(fat, |t| t.link(dir, name).cloned())
+}
+
+/// Links of folder `dir` as (lowercase name, target), for listings.
+pub fn links_in(fat: &mut Fat32, dir: u32) -> Vec<(String, String)> {
+    let dir = norm(fat, dir);
+    with_table(fat, |t| {
+        t.nodes
+            .iter()
+            .filter(|((d, _), _)| *d == dir)
+            .filter_map(|((_, name), node)| node.link.clone().map(|target| (name.clone(), target)))
+            .collect()
+    })
+}
+
+/// Create `name` in `dir` as a link to `target`, which may be relative to
+/// `dir` or start at the root with '/'. It need not exist.
+pub fn symlink(fat: &mut Fat32, dir: u32, name: &str, target: &str) -> Result<(), &'static str> {
+    let target = target.trim().replace('\\', "/");
+    if target.is_empty() || target.len() > TARGET_MAX_BYTES {
+        return Err("destino del enlace invalido");
+    }
+    if find_entry(fat, dir, name).is_some() {
+        return Err("ya existe");
+    }
+    let dir = norm(fat, dir);
+    fat.write_text_file_in_dir(dir, name, &[])?;
+    with_table(fat, |t| {
+        t.node_mut(dir, name).link = Some(target);
+        t.dirty = true;
+    });
+    save(fat)
+}
+
+pub fn valid_xattr_name(name: &str) -> bool {
+    name.len() <= XATTR_KEY_MAX_BYTES
+        && XATTR_NAMESPACES
+            .iter()
+            .any(|ns| name.len() > ns.len() && name.starts_with(ns))
+        && name.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// Name of the entry `name` of `dir` as stored, or an error when it does
+/// not exist.
+fn existing_name(fat: &mut Fat32, dir: u32, name: &str) -> Result<String, &'static str> {
+    find_entry(fat, dir, name).map(|e| e.full_name()).ok_or("no existe")
+}
+
+pub fn get_xattr(fat: &mut Fat32, dir: u32, name: &str, attr: &str) -> Option<Vec<u8>> {
+    let dir = norm(fat, dir);
+    with_table(fat, |t| {
+        t.nodes.get(&key(dir, name)).and_then(|n| n.xattrs.get(attr).cloned())
+    })
+}
+
+pub fn list_xattrs(fat: &mut Fat32, dir: u32, name: &str) -> Vec<(String, Vec<u8>)> {
+    let dir = norm(fat, dir);
+    with_table(fat, |t| {
+        t.nodes
+            .get(&key(dir, name))
+            .map(|n| n.xattrs.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+            .unwrap_or_default()
+    })
+}
+
+pub fn set_xattr(fat: &mut Fat32, dir: u32, name: &str, attr: &str, value: &[u8]) -> Result<(), &'static str> {
+    if !valid_xattr_name(attr) {
+        return Err("nombre de atributo invalido (user.*, security.*, trusted.* o system.*)");
+    }
+    if value.len() > XATTR_VALUE_MAX_BYTES {
+        return Err("valor demasiado largo");
+    }
+    let dir = norm(fat, dir);
+    let stored = existing_name(fat, dir, name)?;
+    authorize_xattr(fat, dir, &stored, attr)?;
+    with_table(fat, |t| {
+        let node = t.node_mut(dir, &stored);
+        if !node.xattrs.contains_key(attr) && node.xattrs.len() >= XATTRS_PER_ENTRY {
+            return Err("demasiados atributos");
+        }
+        node.xattrs.insert(String::from(attr), value.to_vec());
+        t.dirty = true;
+        Ok(())
+    })?;
+    save(fat)
+}
+
+pub fn remove_xattr(fat: &mut Fat32, dir: u32, name: &str, attr: &str) -> Result<bool, &'static str> {
+    let dir = norm(fat, dir);
+    authorize_xattr(fat, dir, name, attr)?;
+    let removed = with_table(fat, |t| {
+        let removed = t
+            .nodes
+            .get_mut(&key(dir, name))
+            .is_some_and(|n| n.xattrs.remove(attr).is_some());
+        if removed {
+            t.prune(dir, name);
+            t.dirty = true;
+        }
+        removed
+    });
+    if removed {
+        save(fat)?;
+    }
+    Ok(removed)
+}
+
+/// Owner, group and mode recorded for the entry `name` of `dir`, if any.
+pub fn perm_of(fat: &mut Fat32, dir: u32, name: &str) -> Option<Perm> {
+    let dir = norm(fat, dir);
+    with_table(fat, |t| t.nodes.get(&key(dir, name)).and_then(|n| n.perm))
+}
+
+/// Record `perm` for the existing entry `name` of `dir`. Callers check who
+/// may do it (`crate::perm::chmod`, `chown`).
+pub fn set_perm(fat: &mut Fat32, dir: u32, name: &str, perm: Perm) -> Result<(), &'static str> {
+    let dir = norm(fat, dir);
+    let stored = existing_name(fat, dir, name)?;
+    with_table(fat, |t| {
+        t.node_mut(dir, &stored).perm = Some(perm);
+        t.dirty = true;
+    });
+    save(fat)
+}
+
+/// `user.*` attributes follow the entry's write permission; the other
+/// namespaces belong to root.
+fn authorize_xattr(fat: &mut Fat32, dir: u32, name: &str, attr: &str) -> Result<(), &'static str> {
+    let cred = crate::perm::acting();
+    if cred.is_root() {
+        return Ok(());
+    }
+    if !attr.starts_with("user.") {
+        return Err("Operacion no permitida");
+    }
+    let is_dir = find_entry(fat, dir, name).is_some_and(|e| e.file_type == FileType::Directory);
+    if crate::perm::entry_perm(fat, dir, name, is_dir).allows(cred, crate::perm::WRITE) {
+        Ok(())
+    } else {
+        Err("Permiso denegado")
+    }
+}
+
+fn printable(value: &[u8]) -> String {
+    match core::str::from_utf8(value) {
+        Ok(text) if text.chars().all(|c| !c.is_control()) => alloc::format!("\"{}\"", text),
+        _ => value.iter().map(|b| alloc::format!("{:02x}", b)).collect(),
+    }
+}
+
+/// `ln -s <destino> <enlace>`, `readlink <ruta>` and
+/// `xattr <ruta> [get <nombre>|set <nombre> <valor>|rm <nombre>]`, with
+/// paths relative to the terminal folder `cwd`.
+pub fn command_lines(fat: &mut Fat32, cwd: u32, verb: &str, args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    if fat.init_status != crate::fat32::InitStatus::Success {
+        out.push(String::from("sin volumen montado"));
+        return out;
+    }
+    let args: Vec<&str> = args.split_whitespace().collect();
+    match verb {
+        "ln" => {
+            let (target, link) = match args.as_slice() {
+                ["-s", target, link] => (*target, *link),
+                _ => {
+                    out.push(String::from("uso: ln -s <destino> <enlace>"));
+                    return out;
+                }
+            };
+            let result = resolve(fat, cwd, link, false).and_then(|place| {
+                if place.entry.is_some() {
+                    return Err("ya existe");
+                }
+                if place.name.is_empty() {
+                    return Err("falta el nombre del enlace");
+                }
+                symlink(fat, place.dir, &place.name, target)
+            });
+            match result {
+                Ok(()) => out.push(alloc::format!("{} -> {}", link, target)),
+                Err(e) => out.push(alloc::format!("ln: {}", e)),
+            }
+        }
+        "readlink" => {
+            let [path] = args.as_slice() else {
+                out.push(String::from("uso: readlink <ruta>"));
+                return out;
+            };
+            match resolve(fat, cwd, path, false) {
+                Ok(place) if place.entry.is_some() => match readlink(fat, place.dir, &place.name) {
+                    Some(target) => out.push(target),
+                    None => out.push(alloc::format!("readlink: {} no es un enlace", path)),
+                },
+                Ok(_) => out.push(alloc::format!("readlink: {} no existe", path)),
+                Err(e) => out.push(alloc::format!("readlink: {}", e)),
+            }
+        }
+        _ => {
+            let Some((path, rest)) = args.split_first() else {
+                out.push(String::from(
+                    "uso: xattr <ruta> [get <nombre>|set <nombre> <valor>|rm <nombre>]",
+                ));
+                return out;
+            };
+            let place = match resolve(fat, cwd, path, true) {
+                Ok(place) if place.entry.is_some() => place,
+                Ok(_) => {
+                    out.push(alloc::format!("xattr: {} no existe", path));
+                    return out;
+                }
+                Err(e) => {
+                    out.push(alloc::format!("xattr: {}", e));
+                    return out;
+                }
+            };
+            match rest {
+                [] => {
+                    let attrs = list_xattrs(fat, place.dir, &place.name);
+                    if attrs.is_empty() {
+                        out.push(String::from("(sin atributos)"));
+                    }
+                    for (attr, value) in attrs.iter() {
+                        out.push(alloc::format!("{} = {}", attr, printable(value)));
+                    }
+                }
+                ["get", attr] => match get_xattr(fat, place.dir, &place.name, attr) {
+                    Some(value) => out.push(printable(&value)),
+                    None => out.push(alloc::format!("xattr: {} no tiene {}", path, attr)),
+                },
+                ["set", attr, value @ ..] if !value.is_empty() => {
+                    let value = value.join(" ");
+                    match set_xattr(fat, place.dir, &place.name, attr, value.as_bytes()) {
+                        Ok(()) => out.push(alloc::format!("{}: {} = \"{}\"", path, attr, value)),
+                        Err(e) => out.push(alloc::format!("xattr: {}", e)),
+                    }
+                }
+                ["rm", attr] => match remove_xattr(fat, place.dir, &place.name, attr) {
+                    Ok(true) => out.push(alloc::format!("{}: {} borrado", path, attr)),
+                    Ok(false) => out.push(alloc::format!("xattr: {} no tiene {}", path, attr)),
+                    Err(e) => out.push(alloc::format!("xattr: {}", e)),
+                },
+                _ => out.push(String::from(
+                    "uso: xattr <ruta> [get <nombre>|set <nombre> <valor>|rm <nombre>]",
+                )),
+            }
+        }
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "fsmeta";
+
+    fn table_round_trip() {
+        let mut table = Table::new(9);
+        table.node_mut(2, "Bin").link = Some(String::from("/apps/bin"));
+        table.node_mut(40, "notas.txt").xattrs.insert(String::from("user.tag"), b"rojo".to_vec());
+        table.node_mut(40, "notas.txt").xattrs.insert(String::from("security.label"), alloc::vec![0, 1, 2]);
+        table.node_mut(40, "privado").perm = Some(Perm { uid: 1000, gid: 100, mode: 0o700 });
+        let encoded = table.encode();
+        let decoded = Table::decode(9, &encoded)?;
+        crate::selftest::ensure_eq(decoded.link(2, "BIN"), Some(&String::from("/apps/bin")), "enlace")?;
+        crate::selftest::ensure_eq(decoded.nodes.len(), 3, "entradas")?;
+        crate::selftest::ensure_eq(
+            decoded.nodes.get(&key(40, "PRIVADO")).and_then(|n| n.perm),
+            Some(Perm { uid: 1000, gid: 100, mode: 0o700 }),
+            "propietario",
+        )?;
+        crate::selftest::ensure_eq(
+            decoded.nodes.get(&key(40, "NOTAS.TXT")).map(|n| n.xattrs.len()),
+            Some(2),
+            "atributos",
+        )?;
+        let mut damaged = encoded.clone();
+        damaged[8] ^= 1;
+        crate::selftest::ensure(Table::decode(9, &damaged).is_err(), "CRC")
+    }
+
+    fn events_move_and_drop_metadata() {
+        use crate::fswatch::{Change, Event};
+        let event = |dir: u32, name: &str, change: Change, cookie: u32| Event {
+            volume: 9,
+            dir,
+            name: String::from(name),
+            change,
+            cookie,
+        };
+        let mut table = Table::new(9);
+        table.node_mut(2, "app").link = Some(String::from("/apps/app.elf"));
+        table.node_mut(2, "doc.txt").xattrs.insert(String::from("user.a"), b"1".to_vec());
+        table.node_mut(5, "x").xattrs.insert(String::from("user.b"), b"2".to_vec());
+
+        table.apply(&event(2, "APP", Change::MovedFrom, 7));
+        table.apply(&event(3, "run", Change::MovedTo, 7));
+        crate::selftest::ensure_eq(table.link(3, "run"), Some(&String::from("/apps/app.elf")), "movido")?;
+        crate::selftest::ensure(table.link(2, "app").is_none(), "origen vacio")?;
+
+        table.apply(&event(3, "run", Change::Written, 0));
+        crate::selftest::ensure(!table.nodes.contains_key(&key(3, "run")), "escrito: ya no es enlace")?;
+        table.apply(&event(2, "doc.txt", Change::Removed, 0));
+        crate::selftest::ensure(!table.nodes.contains_key(&key(2, "doc.txt")), "borrado")?;
+        table.apply(&event(5, "", Change::Removed, 0));
+        crate::selftest::ensure(table.nodes.is_empty(), "carpeta vaciada")?;
+        crate::selftest::ensure(table.dirty, "pendiente de guardar")
+    }
+
+    fn xattr_names() {
+        crate::selftest::ensure(valid_xattr_name("user.color"), "user")?;
+        crate::selftest::ensure(valid_xattr_name("security.capability"), "security")?;
+        crate::selftest::ensure(!valid_xattr_name("user."), "sin nombre")?;
+        crate::selftest::ensure(!valid_xattr_name("color"), "sin espacio de nombres")?;
+        crate::selftest::ensure(!valid_xattr_name("user.con espacio"), "espacio")
+    }
+}
@@ -0,0 +1,422 @@
+//! File change notifications, inotify style.
+//!
+//! The FAT32/exFAT write paths report each entry they create, write,
+//! rename, move or remove through `emit` once the change is on disk. There
+//! are two ways to follow them:
+//!
+//! - `subscribe`: a kernel callback run on the writing thread right after
+//!   the change. It must not touch the filesystem itself, only note what to
+//!   do later (the search index queues the folder, the config store flags a
+//!   reload of its files).
+//! - Watches: `add_watch` on one folder, or on a whole volume, queues the
+//!   events under it until `read_events` drains them. Explorer windows
+//!   refresh this way, and ring 3 gets `SYS_FS_WATCH`, `SYS_FS_READ_EVENTS`
+//!   and `SYS_FS_UNWATCH`.
+//!
+//! A watch holds at most `QUEUE_MAX` events; the ones past that are dropped
+//! and the reader gets a single `Change::Overflow` telling it to rescan.
+//! Events name their volume with `Fat32::volume_id`, since Explorer copies
+//! between drives write through temporary mounts.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::spinlock::SpinLock;
+
+const MAX_SUBSCRIBERS: usize = 8;
+const MAX_WATCHES: usize = 32;
+const MAX_WATCHES_PER_OWNER: usize = 8;
+pub const QUEUE_MAX: usize = 128;
+/// Events kept for the `fswatch` command.
+const RECENT_MAX: usize = 16;
+/// Bytes of a ring 3 event record before the name.
+pub const RECORD_HEADER_BYTES: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Change {
+    /// A folder was created.
+    Created,
+    /// A file was created or its contents rewritten.
+    Written,
+    /// An entry was deleted. An empty name means the whole folder was
+    /// emptied.
+    Removed,
+    /// First half of a rename or move; the `MovedTo` with the same cookie
+    /// follows.
+    MovedFrom,
+    MovedTo,
+    /// Events were dropped; rescan the watched folder.
+    Overflow,
+}
+
+impl Change {
+    /// Value of the change byte in ring 3 records.
+    pub fn code(self) -> u8 {
+        match self {
+            Change::Created => 1,
+            Change::Written => 2,
+            Change::Removed => 3,
+            Change::MovedFrom => 4,
+            Change::MovedTo => 5,
+            Change::Overflow => 6,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Change::Created => "creado",
+            Change::Written => "escrito",
+            Change::Removed => "borrado",
+            Change::MovedFrom => "movido desde",
+            Change::MovedTo => "movido a",
+            Change::Overflow => "desbordado",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Event {
+    /// `Fat32::volume_id` of the volume written.
+    pub volume: u64,
+    /// Cluster of the folder holding the entry.
+    pub dir: u32,
+    pub name: String,
+    pub change: Change,
+    /// Pairs `MovedFrom` with its `MovedTo`; 0 for the other changes.
+    pub cookie: u32,
+}
+
+/// Called on the writing thread after every change.
+pub type SubscriberFn = fn(&Event);
+
+struct Watch {
+    id: u32,
+    owner: String,
+    volume: u64,
+    /// `None` watches the whole volume.
+    dir: Option<u32>,
+    queue: VecDeque<Event>,
+    overflowed: bool,
+}
+
+impl Watch {
+    fn wants(&self, event: &Event) -> bool {
+        self.volume == event.volume && self.dir.is_none_or(|dir| dir == event.dir)
+    }
+
+    fn push(&mut self, event: &Event) {
+        if self.queue.len() >= QUEUE_MAX {
+            self.overflowed = true;
+            return;
+        }
+        self.queue.push_back(event.clone());
+    }
+
+    /// Next event, with the overflow marker once the dropped ones are due.
+    fn pop(&mut self) -> Option<Event> {
+        if let Some(event) = self.queue.pop_front() {
+            return Some(event);
+        }
+        if !core::mem::take(&mut self.overflowed) {
+            return None;
+        }
+        Some(Event {
+            volume: self.volume,
+            dir: self.dir.unwrap_or(0),
+            name: String::new(),
+            change: Change::Overflow,
+            cookie: 0,
+        })
+    }
+
+    fn peek_len(&self) -> Option<usize> {
+        match self.queue.front() {
+            Some(event) => Some(event.name.len()),
+            None if self.overflowed => Some(0),
+            None => None,
+        }
+    }
+}
+
+struct Watches {
+    list: Vec<Watch>,
+    next_id: u32,
+    next_cookie: u32,
+    emitted: u64,
+    recent: VecDeque<Event>,
+}
+
+impl Watches {
+    const fn new() -> Self {
+        Self {
+            list: Vec::new(),
+            next_id: 1,
+            next_cookie: 1,
+            emitted: 0,
+            recent: VecDeque::new(),
+        }
+    }
+
+    fn cookie(&mut self) -> u32 {
+        let cookie = self.next_cookie;
+        self.next_cookie = self.next_cookie.wrapping_add(1).max(1);
+        cookie
+    }
+
+    fn deliver(&mut self, event: &Event) {
+        self.emitted += 1;
+        if self.recent.len() >= RECENT_MAX {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(event.clone());
+        for watch in self.list.iter_mut().filter(|w| w.wants(event)) {
+            watch.push(event);
+        }
+    }
+
+    fn add(&mut self, owner: &str, volume: u64, dir: Option<u32>) -> Result<u32, &'static str> {
+        if self.list.len() >= MAX_WATCHES
+            || self.list.iter().filter(|w| w.owner == owner).count() >= MAX_WATCHES_PER_OWNER
+        {
+            return Err("demasiadas vigilancias abiertas");
+        }
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1).max(1);
+        self.list.push(Watch {
+            id,
+            owner: String::from(owner),
+            volume,
+            dir,
+            queue: VecDeque::new(),
+            overflowed: false,
+        });
+        Ok(id)
+    }
+
+    fn get_mut(&mut self, owner: &str, id: u32) -> Result<&mut Watch, &'static str> {
+        self.list
+            .iter_mut()
+            .find(|w| w.id == id && w.owner == owner)
+            .ok_or("vigilancia desconocida")
+    }
+
+    fn remove(&mut self, owner: &str, id: u32) -> Result<(), &'static str> {
+        let before = self.list.len();
+        self.list.retain(|w| !(w.id == id && w.owner == owner));
+        if self.list.len() == before {
+            return Err("vigilancia desconocida");
+        }
+        Ok(())
+    }
+
+    /// Records that fit in `max_bytes`; the rest stay queued.
+    fn read_encoded(&mut self, owner: &str, id: u32, max_bytes: usize) -> Result<Vec<u8>, &'static str> {
+        let watch = self.get_mut(owner, id)?;
+        let mut out = Vec::new();
+        while let Some(len) = watch.peek_len() {
+            if out.len() + RECORD_HEADER_BYTES + len.min(u16::MAX as usize) > max_bytes {
+                break;
+            }
+            let Some(event) = watch.pop() else {
+                break;
+            };
+            encode_record(&mut out, &event);
+        }
+        Ok(out)
+    }
+}
+
+static WATCHES: SpinLock<Watches> = SpinLock::new(Watches::new());
+static SUBSCRIBERS: SpinLock<Vec<SubscriberFn>> = SpinLock::new(Vec::new());
+
+/// Ring 3 record: change code, a zero byte, the name length (u16 LE), the
+/// cookie (u32 LE), then the name in UTF-8.
+fn encode_record(out: &mut Vec<u8>, event: &Event) {
+    let name = &event.name.as_bytes()[..event.name.len().min(u16::MAX as usize)];
+    out.push(event.change.code());
+    out.push(0);
+    out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    out.extend_from_slice(&event.cookie.to_le_bytes());
+    out.extend_from_slice(name);
+}
+
+fn dispatch(events: &[Event]) {
+    let subscribers: Vec<SubscriberFn> = SUBSCRIBERS.lock().clone();
+    {
+        let mut watches = WATCHES.lock();
+        for event in events.iter() {
+            watches.deliver(event);
+        }
+    }
+    for event in events.iter() {
+        for callback in subscribers.iter() {
+            callback(event);
+        }
+    }
+}
+
+/// Run `callback` after every change. It runs with no lock held but on the
+/// thread that wrote, so it must stay short and must not do filesystem I/O.
+pub fn subscribe(callback: SubscriberFn) -> bool {
+    let mut subscribers = SUBSCRIBERS.lock();
+    if subscribers.len() >= MAX_SUBSCRIBERS {
+        return false;
+    }
+    subscribers.push(callback);
+    true
+}
+
+/// Report a change to the entry `name` of folder `dir`.
+pub fn emit(volume: u64, dir: u32, name: &str, change: Change) {
+    dispatch(&[Event {
+        volume,
+        dir,
+        name: String::from(name),
+        change,
+        cookie: 0,
+    }]);
+}
+
+/// Report a rename (same folder) or a move as a `MovedFrom`/`MovedTo` pair.
+pub fn emit_move(volume: u64, from_dir: u32, from_name: &str, to_dir: u32, to_name: &str) {
+    let cookie = WATCHES.lock().cookie();
+    let event = |dir: u32, name: &str, change: Change| Event {
+        volume,
+        dir,
+        name: String::from(name),
+        change,
+        cookie,
+    };
+    dispatch(&[
+        event(from_dir, from_name, Change::MovedFrom),
+        event(to_dir, to_name, Change::MovedTo),
+    ]);
+}
+
+/// Start queueing the events of folder `dir` (`None`: the whole volume)
+/// for `owner`. Returns the watch id.
+pub fn add_watch(owner: &str, volume: u64, dir: Option<u32>) -> Result<u32, &'static str> {
+    WATCHES.lock().add(owner, volume, dir)
+}
+
+pub fn remove_watch(owner: &str, id: u32) -> Result<(), &'static str> {
+    WATCHES.lock().remove(owner, id)
+}
+
+/// Drain up to `max` queued events of watch `id`.
+pub fn read_events(owner: &str, id: u32, max: usize) -> Result<Vec<Event>, &'static str> {
+    let mut watches = WATCHES.lock();
+    let watch = watches.get_mut(owner, id)?;
+    let mut out = Vec::new();
+    while out.len() < max {
+        match watch.pop() {
+            Some(event) => out.push(event),
+            None => break,
+        }
+    }
+    Ok(out)
+}
+
+/// Drain the queued events of watch `id` as ring 3 records, as many whole
+/// records as fit in `max_bytes`.
+pub fn read_records(owner: &str, id: u32, max_bytes: usize) -> Result<Vec<u8>, &'static str> {
+    WATCHES.lock().read_encoded(owner, id, max_bytes)
+}
+
+/// `fswatch`: open watches and the latest events.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    if !args.trim().is_empty() {
+        out.push(String::from("uso: fswatch"));
+        return out;
+    }
+    let watches = WATCHES.lock();
+    out.push(alloc::format!(
+        "fswatch: {} cambios notificados, {} vigilancias, {} suscriptores",
+        watches.emitted,
+        watches.list.len(),
+        SUBSCRIBERS.lock().len()
+    ));
+    for watch in watches.list.iter() {
+        let target = match watch.dir {
+            Some(dir) => alloc::format!("carpeta {}", dir),
+            None => String::from("volumen entero"),
+        };
+        out.push(alloc::format!(
+            "  #{} {} ({}): {} en cola{}",
+            watch.id,
+            watch.owner,
+            target,
+            watch.queue.len(),
+            if watch.overflowed { ", desbordada" } else { "" }
+        ));
+    }
+    if !watches.recent.is_empty() {
+        out.push(String::from("ultimos cambios:"));
+    }
+    for event in watches.recent.iter().rev() {
+        out.push(alloc::format!(
+            "  [{}] {} {}",
+            event.dir,
+            event.change.label(),
+            event.name
+        ));
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "fswatch";
+
+    fn watches_filter_and_overflow() {
+        let mut watches = Watches::new();
+        let docs = watches.add("app", 7, Some(10))?;
+        let all = watches.add("indice", 7, None)?;
+        let event = |volume: u64, dir: u32, name: &str| Event {
+            volume,
+            dir,
+            name: String::from(name),
+            change: Change::Written,
+            cookie: 0,
+        };
+        watches.deliver(&event(7, 10, "A.TXT"));
+        watches.deliver(&event(7, 11, "B.TXT"));
+        watches.deliver(&event(9, 10, "OTRO.TXT"));
+        crate::selftest::ensure_eq(watches.get_mut("app", docs)?.queue.len(), 1, "solo su carpeta")?;
+        crate::selftest::ensure_eq(watches.get_mut("indice", all)?.queue.len(), 2, "todo el volumen")?;
+        crate::selftest::ensure(watches.get_mut("otro", docs).is_err(), "propietario")?;
+
+        for i in 0..QUEUE_MAX + 5 {
+            watches.deliver(&event(7, 10, alloc::format!("F{}", i).as_str()));
+        }
+        let watch = watches.get_mut("app", docs)?;
+        let mut drained = Vec::new();
+        while let Some(e) = watch.pop() {
+            drained.push(e);
+        }
+        crate::selftest::ensure_eq(drained.len(), QUEUE_MAX + 1, "cola llena + aviso")?;
+        crate::selftest::ensure_eq(drained.last().map(|e| e.change), Some(Change::Overflow), "desborde al final")?;
+        crate::selftest::ensure(watch.pop().is_none(), "vacia")?;
+        watches.remove("app", docs)?;
+        crate::selftest::ensure(watches.remove("app", docs).is_err(), "ya cerrada")
+    }
+
+    fn records_fit_the_buffer() {
+        let mut watches = Watches::new();
+        let id = watches.add("app", 1, Some(2))?;
+        let cookie = watches.cookie();
+        for (name, change) in [("VIEJO.TXT", Change::MovedFrom), ("NUEVO.TXT", Change::MovedTo)] {
+            watches.deliver(&Event { volume: 1, dir: 2, name: String::from(name), change, cookie });
+        }
+        let first = watches.read_encoded("app", id, RECORD_HEADER_BYTES + 12)?;
+        crate::selftest::ensure_eq(first.len(), RECORD_HEADER_BYTES + 9, "un registro")?;
+        crate::selftest::ensure_eq(first[0], Change::MovedFrom.code(), "tipo")?;
+        crate::selftest::ensure_eq(u16::from_le_bytes([first[2], first[3]]), 9, "longitud")?;
+        crate::selftest::ensure_eq(u32::from_le_bytes([first[4], first[5], first[6], first[7]]), cookie, "cookie")?;
+        crate::selftest::ensure_eq(&first[RECORD_HEADER_BYTES..], b"VIEJO.TXT".as_slice(), "nombre")?;
+        let rest = watches.read_encoded("app", id, 4096)?;
+        crate::selftest::ensure_eq(rest.len(), RECORD_HEADER_BYTES + 9, "el segundo sigue en cola")?;
+        crate::selftest::ensure(watches.read_encoded("app", id, 4096)?.is_empty(), "nada mas")
+    }
+}
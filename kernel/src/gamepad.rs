@@ -0,0 +1,1093 @@
+//! Game controllers on the USB stack (xHCI, through the firmware's USB bus
+//! driver).
+//!
+//! Two kinds of device are recognised: HID joysticks/gamepads, whose report
+//! layout comes from the HID report descriptor, and Xbox 360 style XInput
+//! pads (vendor class 0xFF/0x5D/0x01) with their fixed 20-byte report. Both
+//! are normalised to `GamepadState` (15 buttons, two sticks, two triggers)
+//! and diffed into `GamepadEvent`s that the desktop hands to the focused
+//! window. Windows that don't read gamepad events get keys from the mapping
+//! in `gamepad.map.<button>`.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicI32, Ordering};
+
+use crate::config::ConfigValue;
+use crate::spinlock::SpinLock;
+use crate::usbhid::{
+    extended_usage, hid_set_idle, interrupt_in_endpoint, read_bits, read_hid_report_descriptor, HidGlobals,
+    RawUsbIoProtocol, UsbDeviceDescriptor, UsbInterfaceDescriptor,
+};
+
+pub const MAP_PREFIX: &str = "gamepad.map.";
+pub const DEADZONE_KEY: &str = "gamepad.deadzone";
+const DEFAULT_DEADZONE: i32 = 4000;
+const MAX_PADS: usize = 4;
+const EVENT_QUEUE_LIMIT: usize = 256;
+/// Stick movement smaller than this is not worth an event.
+const AXIS_STEP: i32 = 512;
+/// Half deflection turns the left stick into arrow keys.
+const STICK_KEY_THRESHOLD: i32 = 16384;
+const REPORT_MAX_BYTES: usize = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Button {
+    A,
+    B,
+    X,
+    Y,
+    Lb,
+    Rb,
+    Back,
+    Start,
+    LeftStick,
+    RightStick,
+    Guide,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+}
+
+impl Button {
+    pub const ALL: [Button; 15] = [
+        Button::A,
+        Button::B,
+        Button::X,
+        Button::Y,
+        Button::Lb,
+        Button::Rb,
+        Button::Back,
+        Button::Start,
+        Button::LeftStick,
+        Button::RightStick,
+        Button::Guide,
+        Button::DpadUp,
+        Button::DpadDown,
+        Button::DpadLeft,
+        Button::DpadRight,
+    ];
+
+    pub const fn bit(self) -> u16 {
+        1 << self as u16
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Button::A => "a",
+            Button::B => "b",
+            Button::X => "x",
+            Button::Y => "y",
+            Button::Lb => "lb",
+            Button::Rb => "rb",
+            Button::Back => "back",
+            Button::Start => "start",
+            Button::LeftStick => "ls",
+            Button::RightStick => "rs",
+            Button::Guide => "guide",
+            Button::DpadUp => "up",
+            Button::DpadDown => "down",
+            Button::DpadLeft => "left",
+            Button::DpadRight => "right",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|b| b.name() == name)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Axis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 6] = [
+        Axis::LeftX,
+        Axis::LeftY,
+        Axis::RightX,
+        Axis::RightY,
+        Axis::LeftTrigger,
+        Axis::RightTrigger,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Axis::LeftX => "lx",
+            Axis::LeftY => "ly",
+            Axis::RightX => "rx",
+            Axis::RightY => "ry",
+            Axis::LeftTrigger => "lt",
+            Axis::RightTrigger => "rt",
+        }
+    }
+
+    const fn is_trigger(self) -> bool {
+        matches!(self, Axis::LeftTrigger | Axis::RightTrigger)
+    }
+}
+
+/// Sticks are -32767..=32767 with +Y pointing down, like the screen;
+/// triggers are 0..=32767.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct GamepadState {
+    pub buttons: u16,
+    pub axes: [i16; 6],
+}
+
+impl GamepadState {
+    pub fn pressed(&self, button: Button) -> bool {
+        self.buttons & button.bit() != 0
+    }
+
+    pub fn axis(&self, axis: Axis) -> i16 {
+        self.axes[axis as usize]
+    }
+
+    fn set_button(&mut self, button: Button, down: bool) {
+        if down {
+            self.buttons |= button.bit();
+        } else {
+            self.buttons &= !button.bit();
+        }
+    }
+
+    /// Hat position 0 (up) to 7 (up-left) clockwise; `None` is centred.
+    fn set_hat(&mut self, hat: Option<u8>) {
+        let (up, right, down, left) = match hat {
+            Some(0) => (true, false, false, false),
+            Some(1) => (true, true, false, false),
+            Some(2) => (false, true, false, false),
+            Some(3) => (false, true, true, false),
+            Some(4) => (false, false, true, false),
+            Some(5) => (false, false, true, true),
+            Some(6) => (false, false, false, true),
+            Some(7) => (true, false, false, true),
+            _ => (false, false, false, false),
+        };
+        self.set_button(Button::DpadUp, up);
+        self.set_button(Button::DpadRight, right);
+        self.set_button(Button::DpadDown, down);
+        self.set_button(Button::DpadLeft, left);
+    }
+
+    fn with_deadzone(mut self, deadzone: i32) -> Self {
+        for axis in Axis::ALL {
+            if !axis.is_trigger() {
+                self.axes[axis as usize] = apply_deadzone(self.axes[axis as usize], deadzone);
+            }
+        }
+        self
+    }
+}
+
+/// Zero inside the deadzone, the rest of the travel rescaled to full range.
+pub fn apply_deadzone(value: i16, deadzone: i32) -> i16 {
+    let deadzone = deadzone.clamp(0, 32000);
+    let magnitude = (value as i32).abs();
+    if magnitude <= deadzone {
+        return 0;
+    }
+    let scaled = (magnitude - deadzone) * 32767 / (32767 - deadzone);
+    let scaled = scaled.min(32767) as i16;
+    if value < 0 {
+        -scaled
+    } else {
+        scaled
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GamepadEventKind {
+    ButtonDown(Button),
+    ButtonUp(Button),
+    Axis { axis: Axis, value: i16, previous: i16 },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GamepadEvent {
+    pub pad: u8,
+    pub kind: GamepadEventKind,
+}
+
+/// Events that take `reported` to `current`. Axes only report steps of
+/// `AXIS_STEP` or a return to rest / full deflection, so `reported` keeps the
+/// last value each axis was reported at.
+pub fn diff(pad: u8, reported: &mut GamepadState, current: &GamepadState, out: &mut VecDeque<GamepadEvent>) {
+    let changed = reported.buttons ^ current.buttons;
+    for button in Button::ALL {
+        if changed & button.bit() != 0 {
+            let kind = if current.pressed(button) {
+                GamepadEventKind::ButtonDown(button)
+            } else {
+                GamepadEventKind::ButtonUp(button)
+            };
+            out.push_back(GamepadEvent { pad, kind });
+        }
+    }
+    reported.buttons = current.buttons;
+
+    for axis in Axis::ALL {
+        let previous = reported.axis(axis);
+        let value = current.axis(axis);
+        if value == previous {
+            continue;
+        }
+        let step = (value as i32 - previous as i32).abs();
+        let edge = value == 0 || value.unsigned_abs() == 32767;
+        if step >= AXIS_STEP || edge {
+            out.push_back(GamepadEvent {
+                pad,
+                kind: GamepadEventKind::Axis { axis, value, previous },
+            });
+            reported.axes[axis as usize] = value;
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// HID report descriptors
+// ---------------------------------------------------------------------------
+
+/// HID button N (1-based) in the order most DirectInput pads number them.
+const HID_BUTTON_ORDER: [Button; 11] = [
+    Button::A,
+    Button::B,
+    Button::X,
+    Button::Y,
+    Button::Lb,
+    Button::Rb,
+    Button::Back,
+    Button::Start,
+    Button::LeftStick,
+    Button::RightStick,
+    Button::Guide,
+];
+
+const PAGE_GENERIC_DESKTOP: u16 = 0x01;
+const PAGE_SIMULATION: u16 = 0x02;
+const PAGE_BUTTON: u16 = 0x09;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct HidField {
+    report_id: u8,
+    bit_offset: u32,
+    bit_size: u8,
+    page: u16,
+    usage: u16,
+    logical_min: i32,
+    logical_max: i32,
+}
+
+/// Input fields of the joystick/gamepad application collections.
+#[derive(Clone, Debug, Default)]
+pub struct HidLayout {
+    fields: Vec<HidField>,
+    uses_report_ids: bool,
+}
+
+/// `None` unless the descriptor has a Generic Desktop joystick, gamepad or
+/// multi-axis controller collection.
+pub fn parse_report_descriptor(desc: &[u8]) -> Option<HidLayout> {
+    let mut layout = HidLayout::default();
+    let mut globals = HidGlobals::default();
+    let mut stack: Vec<HidGlobals> = Vec::new();
+    let mut usages: Vec<(u16, u16)> = Vec::new();
+    let mut usage_min: Option<(u16, u16)> = None;
+    let mut usage_max: Option<u16> = None;
+    let mut offsets: Vec<(u8, u32)> = Vec::new();
+    let mut depth = 0u32;
+    let mut in_gamepad = false;
+    let mut found = false;
+
+    let mut i = 0usize;
+    while i < desc.len() {
+        let prefix = desc[i];
+        if prefix == 0xFE {
+            // Long item: size, tag, data.
+            let size = *desc.get(i + 1)? as usize;
+            i += 3 + size;
+            continue;
+        }
+        let size = [0usize, 1, 2, 4][(prefix & 0x03) as usize];
+        if i + 1 + size > desc.len() {
+            break;
+        }
+        let raw = &desc[i + 1..i + 1 + size];
+        let data = raw.iter().rev().fold(0u32, |acc, b| (acc << 8) | *b as u32);
+        let sdata = match size {
+            1 => data as u8 as i8 as i32,
+            2 => data as u16 as i16 as i32,
+            _ => data as i32,
+        };
+        i += 1 + size;
+
+        match prefix & 0xFC {
+            // Global items.
+            0x04 => globals.page = data as u16,
+            0x14 => globals.logical_min = sdata,
+            0x24 => {
+                globals.logical_max_signed = sdata;
+                globals.logical_max_unsigned = data as i32;
+            }
+            0x74 => globals.report_size = data,
+            0x84 => {
+                globals.report_id = data as u8;
+                layout.uses_report_ids = true;
+            }
+            0x94 => globals.report_count = data,
+            0xA4 => stack.push(globals),
+            0xB4 => globals = stack.pop().unwrap_or(globals),
+            // Local items; a four-byte usage carries its own page.
+            0x08 => usages.push(extended_usage(globals.page, data, size)),
+            0x18 => usage_min = Some(extended_usage(globals.page, data, size)),
+            0x28 => usage_max = Some(data as u16),
+            // Main items.
+            0xA0 => {
+                depth += 1;
+                if depth == 1 && data == 0x01 {
+                    in_gamepad = matches!(usages.first(), Some(&(PAGE_GENERIC_DESKTOP, 0x04 | 0x05 | 0x08)));
+                    found |= in_gamepad;
+                }
+                usages.clear();
+                usage_min = None;
+                usage_max = None;
+            }
+            0xC0 => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    in_gamepad = false;
+                }
+                usages.clear();
+                usage_min = None;
+                usage_max = None;
+            }
+            0x80 => {
+                let constant = data & 0x01 != 0;
+                let variable = data & 0x02 != 0;
+                let bits = globals.report_size;
+                let cursor = match offsets.iter_mut().find(|(id, _)| *id == globals.report_id) {
+                    Some((_, cursor)) => cursor,
+                    None => {
+                        offsets.push((globals.report_id, 0));
+                        &mut offsets.last_mut().unwrap().1
+                    }
+                };
+                for n in 0..globals.report_count {
+                    let usage = if !usages.is_empty() {
+                        Some(usages[(n as usize).min(usages.len() - 1)])
+                    } else {
+                        usage_min
+                            .map(|(page, min)| (page, min.saturating_add(n as u16)))
+                            .filter(|(_, usage)| usage_max.is_none_or(|max| *usage <= max))
+                    };
+                    if let (true, false, true, Some((page, usage))) = (in_gamepad, constant, variable, usage) {
+                        if (1..=32).contains(&bits) {
+                            layout.fields.push(HidField {
+                                report_id: globals.report_id,
+                                bit_offset: *cursor + n * bits,
+                                bit_size: bits as u8,
+                                page,
+                                usage,
+                                logical_min: globals.logical_min,
+                                logical_max: globals.logical_max(),
+                            });
+                        }
+                    }
+                }
+                *cursor += bits.saturating_mul(globals.report_count);
+                usages.clear();
+                usage_min = None;
+                usage_max = None;
+            }
+            0x90 | 0xB0 => {
+                usages.clear();
+                usage_min = None;
+                usage_max = None;
+            }
+            _ => {}
+        }
+    }
+
+    (found && !layout.fields.is_empty()).then_some(layout)
+}
+
+fn scale_stick(value: i32, min: i32, max: i32) -> i16 {
+    if max <= min {
+        return 0;
+    }
+    let scaled = (value as i64 - min as i64) * 65535 / (max as i64 - min as i64) - 32768;
+    scaled.clamp(-32767, 32767) as i16
+}
+
+fn scale_trigger(value: i32, min: i32, max: i32) -> i16 {
+    if max <= min {
+        return 0;
+    }
+    let scaled = (value as i64 - min as i64) * 32767 / (max as i64 - min as i64);
+    scaled.clamp(0, 32767) as i16
+}
+
+impl HidLayout {
+    /// State after input report `report`, starting from `previous` so pads
+    /// that split their controls over several report IDs keep the rest.
+    pub fn decode(&self, report: &[u8], previous: &GamepadState) -> Option<GamepadState> {
+        let (report_id, payload) = if self.uses_report_ids {
+            (*report.first()?, &report[1..])
+        } else {
+            (0, report)
+        };
+        let mut state = *previous;
+        let mut matched = false;
+        for field in self.fields.iter().filter(|f| f.report_id == report_id) {
+            let Some(raw) = read_bits(payload, field.bit_offset, field.bit_size) else {
+                continue;
+            };
+            matched = true;
+            let value = if field.logical_min < 0 && field.bit_size < 32 {
+                let shift = 32 - field.bit_size as u32;
+                ((raw << shift) as i32) >> shift
+            } else {
+                raw as i32
+            };
+            let (min, max) = (field.logical_min, field.logical_max);
+            match (field.page, field.usage) {
+                (PAGE_BUTTON, n @ 1..=11) => state.set_button(HID_BUTTON_ORDER[n as usize - 1], value != 0),
+                (PAGE_GENERIC_DESKTOP, 0x30) => state.axes[Axis::LeftX as usize] = scale_stick(value, min, max),
+                (PAGE_GENERIC_DESKTOP, 0x31) => state.axes[Axis::LeftY as usize] = scale_stick(value, min, max),
+                (PAGE_GENERIC_DESKTOP, 0x32) => state.axes[Axis::RightX as usize] = scale_stick(value, min, max),
+                (PAGE_GENERIC_DESKTOP, 0x35) => state.axes[Axis::RightY as usize] = scale_stick(value, min, max),
+                (PAGE_GENERIC_DESKTOP, 0x33) | (PAGE_SIMULATION, 0xC5) => {
+                    state.axes[Axis::LeftTrigger as usize] = scale_trigger(value, min, max)
+                }
+                (PAGE_GENERIC_DESKTOP, 0x34) | (PAGE_SIMULATION, 0xC4) => {
+                    state.axes[Axis::RightTrigger as usize] = scale_trigger(value, min, max)
+                }
+                (PAGE_GENERIC_DESKTOP, 0x39) => {
+                    let span = max - min;
+                    let hat = if value < min || value > max {
+                        None
+                    } else if span == 3 {
+                        Some(((value - min) * 2) as u8)
+                    } else {
+                        Some((value - min) as u8)
+                    };
+                    state.set_hat(hat);
+                }
+                _ => {}
+            }
+        }
+        matched.then_some(state)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// XInput (Xbox 360 wired pads and compatibles)
+// ---------------------------------------------------------------------------
+
+const XINPUT_BUTTONS: [(u16, Button); 15] = [
+    (0x0001, Button::DpadUp),
+    (0x0002, Button::DpadDown),
+    (0x0004, Button::DpadLeft),
+    (0x0008, Button::DpadRight),
+    (0x0010, Button::Start),
+    (0x0020, Button::Back),
+    (0x0040, Button::LeftStick),
+    (0x0080, Button::RightStick),
+    (0x0100, Button::Lb),
+    (0x0200, Button::Rb),
+    (0x0400, Button::Guide),
+    (0x1000, Button::A),
+    (0x2000, Button::B),
+    (0x4000, Button::X),
+    (0x8000, Button::Y),
+];
+
+/// Input report: type 0x00, length 0x14, buttons, LT, RT, LX, LY, RX, RY.
+/// LED and rumble status messages return `None`.
+pub fn decode_xinput(report: &[u8]) -> Option<GamepadState> {
+    if report.len() < 14 || report[0] != 0x00 || report[1] < 0x14 {
+        return None;
+    }
+    let word = |at: usize| u16::from_le_bytes([report[at], report[at + 1]]);
+    let stick = |at: usize, flip: bool| {
+        let v = word(at) as i16 as i32;
+        (if flip { -v } else { v }).clamp(-32767, 32767) as i16
+    };
+    let mut state = GamepadState::default();
+    let raw_buttons = word(2);
+    for (mask, button) in XINPUT_BUTTONS {
+        state.set_button(button, raw_buttons & mask != 0);
+    }
+    state.axes[Axis::LeftTrigger as usize] = (report[4] as i32 * 32767 / 255) as i16;
+    state.axes[Axis::RightTrigger as usize] = (report[5] as i32 * 32767 / 255) as i16;
+    state.axes[Axis::LeftX as usize] = stick(6, false);
+    state.axes[Axis::LeftY as usize] = stick(8, true);
+    state.axes[Axis::RightX as usize] = stick(10, false);
+    state.axes[Axis::RightY as usize] = stick(12, true);
+    Some(state)
+}
+
+// ---------------------------------------------------------------------------
+// Key mapping
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyTarget {
+    Char(char),
+    Special(crate::gui::SpecialKey),
+}
+
+const DEFAULT_MAP: [(Button, &str); 9] = [
+    (Button::DpadUp, "up"),
+    (Button::DpadDown, "down"),
+    (Button::DpadLeft, "left"),
+    (Button::DpadRight, "right"),
+    (Button::A, "enter"),
+    (Button::B, "esc"),
+    (Button::X, "space"),
+    (Button::Start, "enter"),
+    (Button::Back, "esc"),
+];
+
+/// Key names accepted by `gamepad map`: enter, esc, space, tab, backspace,
+/// up, down, left, right, none or a single character.
+pub fn parse_key(name: &str) -> Option<Option<KeyTarget>> {
+    use crate::gui::SpecialKey;
+    let target = match name {
+        "none" => return Some(None),
+        "enter" => KeyTarget::Char('\n'),
+        "esc" => KeyTarget::Char('\x1b'),
+        "space" => KeyTarget::Char(' '),
+        "tab" => KeyTarget::Char('\t'),
+        "backspace" => KeyTarget::Char('\x08'),
+        "up" => KeyTarget::Special(SpecialKey::Up),
+        "down" => KeyTarget::Special(SpecialKey::Down),
+        "left" => KeyTarget::Special(SpecialKey::Left),
+        "right" => KeyTarget::Special(SpecialKey::Right),
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) if ch.is_ascii_graphic() => KeyTarget::Char(ch),
+                _ => return None,
+            }
+        }
+    };
+    Some(Some(target))
+}
+
+fn default_binding(button: Button) -> &'static str {
+    DEFAULT_MAP
+        .iter()
+        .find(|(b, _)| *b == button)
+        .map(|(_, key)| *key)
+        .unwrap_or("none")
+}
+
+/// Key name bound to `button`: the config entry if valid, else the default.
+pub fn binding(button: Button) -> String {
+    let key = alloc::format!("{}{}", MAP_PREFIX, button.name());
+    match crate::config::get(key.as_str()) {
+        Some(ConfigValue::Str(name)) if parse_key(name.as_str()).is_some() => name,
+        _ => String::from(default_binding(button)),
+    }
+}
+
+/// -1, 0 or 1 for a stick value around the key threshold.
+fn stick_zone(value: i16) -> i32 {
+    match value as i32 {
+        v if v <= -STICK_KEY_THRESHOLD => -1,
+        v if v >= STICK_KEY_THRESHOLD => 1,
+        _ => 0,
+    }
+}
+
+/// The d-pad button an event stands for when turned into a key: presses, and
+/// the left stick entering a direction.
+fn key_button(event: &GamepadEvent) -> Option<Button> {
+    match event.kind {
+        GamepadEventKind::ButtonDown(button) => Some(button),
+        GamepadEventKind::Axis { axis, value, previous } => {
+            let zone = stick_zone(value);
+            if zone == 0 || zone == stick_zone(previous) {
+                return None;
+            }
+            match (axis, zone) {
+                (Axis::LeftX, -1) => Some(Button::DpadLeft),
+                (Axis::LeftX, _) => Some(Button::DpadRight),
+                (Axis::LeftY, -1) => Some(Button::DpadUp),
+                (Axis::LeftY, _) => Some(Button::DpadDown),
+                _ => None,
+            }
+        }
+        GamepadEventKind::ButtonUp(_) => None,
+    }
+}
+
+/// Key press for windows that only understand the keyboard.
+pub fn keyboard_event(event: &GamepadEvent) -> Option<crate::gui::KeyboardEvent> {
+    let button = key_button(event)?;
+    let target = parse_key(binding(button).as_str()).flatten()?;
+    let (key, special) = match target {
+        KeyTarget::Char(ch) => (Some(ch), None),
+        KeyTarget::Special(special) => (None, Some(special)),
+    };
+    Some(crate::gui::KeyboardEvent {
+        key,
+        special,
+        down: true,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Devices
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PadKind {
+    Hid,
+    XInput,
+}
+
+impl PadKind {
+    const fn label(self) -> &'static str {
+        match self {
+            PadKind::Hid => "HID",
+            PadKind::XInput => "XInput",
+        }
+    }
+}
+
+struct Pad {
+    proto: usize,
+    endpoint: u8,
+    packet_len: usize,
+    vendor: u16,
+    product: u16,
+    kind: PadKind,
+    layout: HidLayout,
+    state: GamepadState,
+    reported: GamepadState,
+    reports: u64,
+    errors: u32,
+}
+
+struct Gamepads {
+    pads: Vec<Pad>,
+    events: VecDeque<GamepadEvent>,
+    scanned: bool,
+}
+
+impl Gamepads {
+    const fn new() -> Self {
+        Self {
+            pads: Vec::new(),
+            events: VecDeque::new(),
+            scanned: false,
+        }
+    }
+}
+
+static GAMEPADS: SpinLock<Gamepads> = SpinLock::new(Gamepads::new());
+static DEADZONE: AtomicI32 = AtomicI32::new(DEFAULT_DEADZONE);
+
+fn on_deadzone_changed(_key: &str, value: Option<&ConfigValue>) {
+    let deadzone = match value {
+        Some(ConfigValue::Int(n)) => (*n).clamp(0, 32000) as i32,
+        _ => DEFAULT_DEADZONE,
+    };
+    DEADZONE.store(deadzone, Ordering::Release);
+}
+
+/// Follow `gamepad.deadzone` from now on.
+pub fn init() {
+    crate::config::watch(DEADZONE_KEY, on_deadzone_changed);
+}
+
+unsafe fn probe(proto: *mut RawUsbIoProtocol) -> Option<Pad> {
+    let mut iface = UsbInterfaceDescriptor::default();
+    if ((*proto).get_interface_descriptor)(proto, &mut iface).is_error() {
+        return None;
+    }
+    let kind = match (
+        iface.interface_class,
+        iface.interface_sub_class,
+        iface.interface_protocol,
+    ) {
+        (0xFF, 0x5D, 0x01) => PadKind::XInput,
+        // Boot keyboards and mice belong to the firmware drivers.
+        (0x03, 0x01, 0x01 | 0x02) => return None,
+        (0x03, _, _) => PadKind::Hid,
+        _ => return None,
+    };
+    let mut device = UsbDeviceDescriptor::default();
+    let _ = ((*proto).get_device_descriptor)(proto, &mut device);
+    let (endpoint, max_packet) = interrupt_in_endpoint(proto, iface.num_endpoints)?;
+    let layout = match kind {
+        PadKind::Hid => {
+            let desc = read_hid_report_descriptor(proto, iface.interface_number)?;
+            let layout = parse_report_descriptor(&desc)?;
+            hid_set_idle(proto, iface.interface_number);
+            layout
+        }
+        PadKind::XInput => HidLayout::default(),
+    };
+    Some(Pad {
+        proto: proto as usize,
+        endpoint,
+        packet_len: (max_packet as usize).clamp(1, REPORT_MAX_BYTES),
+        vendor: device.id_vendor,
+        product: device.id_product,
+        kind,
+        layout,
+        state: GamepadState::default(),
+        reported: GamepadState::default(),
+        reports: 0,
+        errors: 0,
+    })
+}
+
+/// Find USB gamepads. Called when the desktop starts and again after the
+/// firmware may have re-enumerated the bus.
+pub fn reset_uefi() {
+    use uefi::boot::{OpenProtocolAttributes, OpenProtocolParams};
+
+    let handles = uefi::boot::find_handles::<RawUsbIoProtocol>().unwrap_or_default();
+    let agent = uefi::boot::image_handle();
+    let mut pads = Vec::new();
+    for handle in handles.iter() {
+        if pads.len() >= MAX_PADS {
+            break;
+        }
+        let params = OpenProtocolParams {
+            handle: *handle,
+            agent,
+            controller: None,
+        };
+        let Ok(scoped) =
+            (unsafe { uefi::boot::open_protocol::<RawUsbIoProtocol>(params, OpenProtocolAttributes::GetProtocol) })
+        else {
+            continue;
+        };
+        let raw = &*scoped as *const RawUsbIoProtocol as *mut RawUsbIoProtocol;
+        if let Some(pad) = unsafe { probe(raw) } {
+            core::mem::forget(scoped);
+            crate::klog::log(
+                crate::klog::Level::Info,
+                alloc::format!(
+                    "gamepad {}: {:04x}:{:04x} {} ep {:#04x}",
+                    pads.len(),
+                    pad.vendor,
+                    pad.product,
+                    pad.kind.label(),
+                    pad.endpoint
+                )
+                .as_str(),
+            );
+            pads.push(pad);
+        }
+    }
+    // The registry is only readable once the volume is mounted.
+    on_deadzone_changed(DEADZONE_KEY, crate::config::get(DEADZONE_KEY).as_ref());
+    let mut gamepads = GAMEPADS.lock();
+    gamepads.pads = pads;
+    gamepads.events.clear();
+    gamepads.scanned = true;
+}
+
+fn ensure_scanned() {
+    if !GAMEPADS.lock().scanned {
+        reset_uefi();
+    }
+}
+
+fn poll_devices(gamepads: &mut Gamepads) {
+    let deadzone = DEADZONE.load(Ordering::Acquire);
+    let Gamepads { pads, events, .. } = gamepads;
+    for (index, pad) in pads.iter_mut().enumerate() {
+        let proto = pad.proto as *mut RawUsbIoProtocol;
+        let mut buf = [0u8; REPORT_MAX_BYTES];
+        let mut len = pad.packet_len;
+        let mut usb_status = 0u32;
+        let status = unsafe {
+            ((*proto).sync_interrupt_transfer)(
+                proto,
+                pad.endpoint,
+                buf.as_mut_ptr() as *mut core::ffi::c_void,
+                &mut len,
+                1,
+                &mut usb_status,
+            )
+        };
+        if status == uefi::Status::TIMEOUT {
+            continue;
+        }
+        if status.is_error() {
+            pad.errors = pad.errors.saturating_add(1);
+            continue;
+        }
+        let report = &buf[..len.min(REPORT_MAX_BYTES)];
+        let decoded = match pad.kind {
+            PadKind::XInput => decode_xinput(report),
+            PadKind::Hid => pad.layout.decode(report, &pad.state),
+        };
+        let Some(state) = decoded else {
+            continue;
+        };
+        pad.reports += 1;
+        pad.state = state;
+        diff(index as u8, &mut pad.reported, &state.with_deadzone(deadzone), events);
+    }
+    while events.len() > EVENT_QUEUE_LIMIT {
+        events.pop_front();
+    }
+}
+
+/// Next gamepad event, reading the devices when the queue is empty.
+pub fn poll_event() -> Option<GamepadEvent> {
+    let mut gamepads = GAMEPADS.lock();
+    if gamepads.pads.is_empty() {
+        return None;
+    }
+    if gamepads.events.is_empty() {
+        poll_devices(&mut gamepads);
+    }
+    gamepads.events.pop_front()
+}
+
+pub fn pad_count() -> usize {
+    GAMEPADS.lock().pads.len()
+}
+
+/// Current state of every pad, deadzone applied.
+pub fn snapshot() -> Vec<GamepadState> {
+    let deadzone = DEADZONE.load(Ordering::Acquire);
+    let mut gamepads = GAMEPADS.lock();
+    poll_devices(&mut gamepads);
+    gamepads.events.clear();
+    gamepads
+        .pads
+        .iter()
+        .map(|pad| pad.state.with_deadzone(deadzone))
+        .collect()
+}
+
+pub fn state_line(pad: usize, state: &GamepadState) -> String {
+    let mut line = alloc::format!("#{}", pad);
+    for axis in Axis::ALL {
+        line.push_str(alloc::format!(" {}={}", axis.name(), state.axis(axis)).as_str());
+    }
+    line.push_str(" [");
+    let mut first = true;
+    for button in Button::ALL.iter().filter(|b| state.pressed(**b)) {
+        if !first {
+            line.push(' ');
+        }
+        line.push_str(button.name());
+        first = false;
+    }
+    line.push(']');
+    line
+}
+
+/// Device list for `gamepad list` and `hwinfo input`.
+pub fn status_lines() -> Vec<String> {
+    let gamepads = GAMEPADS.lock();
+    gamepads
+        .pads
+        .iter()
+        .enumerate()
+        .map(|(i, pad)| {
+            alloc::format!(
+                "mando {}: {:04x}:{:04x} {} ep {:#04x}, {} informes, {} errores",
+                i,
+                pad.vendor,
+                pad.product,
+                pad.kind.label(),
+                pad.endpoint,
+                pad.reports,
+                pad.errors
+            )
+        })
+        .collect()
+}
+
+fn map_lines() -> Vec<String> {
+    let mut out = Vec::new();
+    out.push(String::from("Asignacion de botones a teclas:"));
+    for button in Button::ALL {
+        out.push(alloc::format!("  {:<6} -> {}", button.name(), binding(button)));
+    }
+    out.push(String::from("El stick izquierdo usa las teclas de up/down/left/right."));
+    out
+}
+
+/// Shared implementation of `gamepad [list|rescan|test|map ...|deadzone <n>]`.
+/// The text shell runs `test` live; this returns one snapshot.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim();
+    let (verb, rest) = match args.split_once(' ') {
+        Some((verb, rest)) => (verb, rest.trim()),
+        None => (args, ""),
+    };
+    let mut out = Vec::new();
+    match verb {
+        "" | "list" | "status" | "rescan" => {
+            if verb == "rescan" {
+                reset_uefi();
+            } else {
+                ensure_scanned();
+            }
+            let lines = status_lines();
+            if lines.is_empty() {
+                out.push(String::from("No hay mandos USB (HID o XInput)."));
+            }
+            out.extend(lines);
+            out.push(alloc::format!("Zona muerta: {}", DEADZONE.load(Ordering::Acquire)));
+        }
+        "test" => {
+            ensure_scanned();
+            let states = snapshot();
+            if states.is_empty() {
+                out.push(String::from("No hay mandos USB (HID o XInput)."));
+            }
+            for (i, state) in states.iter().enumerate() {
+                out.push(state_line(i, state));
+            }
+        }
+        "map" => {
+            let mut parts = rest.split_whitespace();
+            match (parts.next(), parts.next()) {
+                (None, _) => out.extend(map_lines()),
+                (Some("reset"), None) => {
+                    for button in Button::ALL {
+                        let key = alloc::format!("{}{}", MAP_PREFIX, button.name());
+                        if let Err(e) = crate::config::unset(key.as_str()) {
+                            out.push(alloc::format!("config: {}", e));
+                            break;
+                        }
+                    }
+                    out.push(String::from("Asignacion restablecida."));
+                }
+                (Some(name), Some(key)) => {
+                    let key = key.to_ascii_lowercase();
+                    match (
+                        Button::from_name(name.to_ascii_lowercase().as_str()),
+                        parse_key(key.as_str()),
+                    ) {
+                        (None, _) => out.push(alloc::format!("gamepad: boton desconocido '{}'", name)),
+                        (_, None) => out.push(alloc::format!("gamepad: tecla desconocida '{}'", key)),
+                        (Some(button), Some(_)) => {
+                            let config_key = alloc::format!("{}{}", MAP_PREFIX, button.name());
+                            match crate::config::set(config_key.as_str(), ConfigValue::Str(key.clone())) {
+                                Ok(()) => out.push(alloc::format!("{} -> {}", button.name(), key)),
+                                Err(e) => out.push(alloc::format!("config: {}", e)),
+                            }
+                        }
+                    }
+                }
+                _ => out.push(String::from("Uso: gamepad map [<boton> <tecla|none>|reset]")),
+            }
+        }
+        "deadzone" => match rest.parse::<i64>() {
+            Ok(n) if (0..=32000).contains(&n) => match crate::config::set(DEADZONE_KEY, ConfigValue::Int(n)) {
+                Ok(()) => out.push(alloc::format!("Zona muerta: {}", n)),
+                Err(e) => out.push(alloc::format!("config: {}", e)),
+            },
+            _ => out.push(String::from("Uso: gamepad deadzone <0..32000>")),
+        },
+        _ => out.push(String::from(
+            "Uso: gamepad [list|rescan|test|map [<boton> <tecla>]|deadzone <n>]",
+        )),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "gamepad";
+
+    fn hid_descriptor_yields_sticks_hat_and_buttons() {
+        // Generic pad: X/Y/Z/Rz bytes, a 4-bit hat, 4 padding bits, 12 buttons.
+        const DESC: &[u8] = &[
+            0x05, 0x01, 0x09, 0x05, 0xA1, 0x01, // Gamepad application
+            0x09, 0x30, 0x09, 0x31, 0x09, 0x32, 0x09, 0x35, //
+            0x15, 0x00, 0x26, 0xFF, 0x00, 0x75, 0x08, 0x95, 0x04, 0x81, 0x02, //
+            0x09, 0x39, 0x15, 0x00, 0x25, 0x07, 0x75, 0x04, 0x95, 0x01, 0x81, 0x42, //
+            0x75, 0x04, 0x95, 0x01, 0x81, 0x03, //
+            0x05, 0x09, 0x19, 0x01, 0x29, 0x0C, 0x15, 0x00, 0x25, 0x01, //
+            0x75, 0x01, 0x95, 0x0C, 0x81, 0x02, 0x75, 0x04, 0x95, 0x01, 0x81, 0x03, //
+            0xC0,
+        ];
+        let layout = parse_report_descriptor(DESC).ok_or("no es un mando")?;
+        let state = layout
+            .decode(&[0x00, 0xFF, 0x80, 0x80, 0x02, 0x09, 0x00], &GamepadState::default())
+            .ok_or("informe")?;
+        crate::selftest::ensure_eq(state.axis(Axis::LeftX), -32767, "lx")?;
+        crate::selftest::ensure_eq(state.axis(Axis::LeftY), 32767, "ly")?;
+        crate::selftest::ensure(state.pressed(Button::DpadRight), "hat 2 = derecha")?;
+        crate::selftest::ensure(state.pressed(Button::A) && state.pressed(Button::Y), "botones 1 y 4")?;
+        crate::selftest::ensure(!state.pressed(Button::B), "boton 2 suelto")?;
+        crate::selftest::ensure(parse_report_descriptor(&[0x05, 0x01, 0x09, 0x06, 0xA1, 0x01, 0xC0]).is_none(), "teclado")
+    }
+
+    fn xinput_report_is_normalised() {
+        let mut report = [0u8; 20];
+        report[1] = 0x14;
+        report[2..4].copy_from_slice(&0x1001u16.to_le_bytes());
+        report[5] = 0xFF;
+        report[8..10].copy_from_slice(&i16::MAX.to_le_bytes());
+        report[10..12].copy_from_slice(&i16::MIN.to_le_bytes());
+        let state = decode_xinput(&report).ok_or("informe")?;
+        crate::selftest::ensure(state.pressed(Button::A) && state.pressed(Button::DpadUp), "A y arriba")?;
+        crate::selftest::ensure_eq(state.axis(Axis::RightTrigger), 32767, "rt")?;
+        crate::selftest::ensure_eq(state.axis(Axis::LeftY), -32767, "ly hacia arriba es negativo")?;
+        crate::selftest::ensure_eq(state.axis(Axis::RightX), -32767, "rx")?;
+        crate::selftest::ensure(decode_xinput(&[0x01, 0x03, 0x06]).is_none(), "mensaje de LED")
+    }
+
+    fn diff_reports_buttons_and_coarse_axes() {
+        let mut reported = GamepadState::default();
+        let mut current = GamepadState::default();
+        let mut out = VecDeque::new();
+        current.set_button(Button::B, true);
+        current.axes[Axis::LeftX as usize] = apply_deadzone(3000, DEFAULT_DEADZONE);
+        diff(1, &mut reported, &current, &mut out);
+        crate::selftest::ensure_eq(out.len(), 1, "el stick dentro de la zona muerta no cuenta")?;
+        crate::selftest::ensure_eq(out[0].kind, GamepadEventKind::ButtonDown(Button::B), "pulsado")?;
+        out.clear();
+        current.axes[Axis::LeftX as usize] = 200;
+        diff(1, &mut reported, &current, &mut out);
+        crate::selftest::ensure(out.is_empty(), "paso menor que AXIS_STEP")?;
+        current.axes[Axis::LeftX as usize] = 32767;
+        diff(1, &mut reported, &current, &mut out);
+        crate::selftest::ensure_eq(
+            out.pop_front().map(|e| e.kind),
+            Some(GamepadEventKind::Axis { axis: Axis::LeftX, value: 32767, previous: 0 }),
+            "eje",
+        )
+    }
+
+    fn default_map_turns_stick_into_arrows() {
+        let right = GamepadEvent {
+            pad: 0,
+            kind: GamepadEventKind::Axis { axis: Axis::LeftX, value: 20000, previous: 1000 },
+        };
+        crate::selftest::ensure_eq(key_button(&right), Some(Button::DpadRight), "entrar en la zona")?;
+        let held = GamepadEvent {
+            pad: 0,
+            kind: GamepadEventKind::Axis { axis: Axis::LeftX, value: 30000, previous: 20000 },
+        };
+        crate::selftest::ensure_eq(key_button(&held), None, "sin repeticion")?;
+        crate::selftest::ensure_eq(parse_key(default_binding(Button::A)), Some(Some(KeyTarget::Char('\n'))), "A")?;
+        crate::selftest::ensure_eq(parse_key("none"), Some(None), "none")?;
+        crate::selftest::ensure_eq(parse_key("f13"), None, "desconocida")
+    }
+}
@@ -0,0 +1,555 @@
+//! Display color adjustment: gamma ramp, white point and night light.
+//!
+//! The adjustment is a per-channel 256-entry lookup table built from
+//! `display.gamma` (percent, 100 = unchanged) and a color temperature in
+//! kelvin (6500 = unchanged). The table goes to the Intel Xe pipe palette
+//! when that driver has the display engine mapped, otherwise
+//! `framebuffer::present` shades every frame with it in software
+//! (`display.gamma_backend` forces one or the other).
+//!
+//! Night light lowers the temperature to `display.night_light.temperature`
+//! always (`on`) or between `display.night_light.from` and `.to` local time
+//! (`schedule`), following the wall clock. The compositor calls `service`
+//! from its background tasks; it rebuilds the table only when the effective
+//! values change.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::config::ConfigValue;
+use crate::spinlock::SpinLock;
+
+pub const GAMMA_KEY: &str = "display.gamma";
+pub const TEMPERATURE_KEY: &str = "display.temperature";
+pub const BACKEND_KEY: &str = "display.gamma_backend";
+pub const NIGHT_KEY: &str = "display.night_light";
+pub const NIGHT_TEMPERATURE_KEY: &str = "display.night_light.temperature";
+pub const NIGHT_FROM_KEY: &str = "display.night_light.from";
+pub const NIGHT_TO_KEY: &str = "display.night_light.to";
+
+pub const GAMMA_MIN: u32 = 50;
+pub const GAMMA_MAX: u32 = 300;
+pub const TEMPERATURE_MIN: u32 = 1000;
+pub const NEUTRAL_TEMPERATURE: u32 = 6500;
+const DEFAULT_NIGHT_TEMPERATURE: u32 = 3400;
+const DEFAULT_NIGHT_FROM: u32 = 21 * 60;
+const DEFAULT_NIGHT_TO: u32 = 7 * 60;
+/// The schedule is re-evaluated at most this often.
+const SERVICE_INTERVAL_MS: u64 = 1000;
+
+/// Channel gains in per mille, every 500 K from 1000 K to 6500 K
+/// (blackbody approximation, normalised to 6500 K).
+const WHITEPOINT: [(u32, u32, u32); 12] = [
+    (1000, 267, 0),
+    (1000, 426, 0),
+    (1000, 539, 56),
+    (1000, 626, 280),
+    (1000, 697, 440),
+    (1000, 758, 563),
+    (1000, 810, 664),
+    (1000, 856, 750),
+    (1000, 897, 824),
+    (1000, 935, 889),
+    (1000, 969, 947),
+    (1000, 1000, 1000),
+];
+
+/// 2^(2^-k) in Q16 for k = 1..=16.
+const EXP2_FRACTIONS: [u64; 16] = [
+    92682, 77936, 71468, 68438, 66971, 66250, 65892, 65714, 65625, 65580, 65558, 65547, 65542, 65539, 65537, 65537,
+];
+
+/// Channel gains (per mille) for a color temperature.
+pub fn whitepoint(kelvin: u32) -> (u32, u32, u32) {
+    let kelvin = kelvin.clamp(TEMPERATURE_MIN, NEUTRAL_TEMPERATURE);
+    let offset = kelvin - TEMPERATURE_MIN;
+    let index = (offset / 500) as usize;
+    if index + 1 >= WHITEPOINT.len() {
+        return WHITEPOINT[WHITEPOINT.len() - 1];
+    }
+    let t = offset % 500;
+    let (a, b) = (WHITEPOINT[index], WHITEPOINT[index + 1]);
+    let lerp = |x: u32, y: u32| (x * (500 - t) + y * t) / 500;
+    (lerp(a.0, b.0), lerp(a.1, b.1), lerp(a.2, b.2))
+}
+
+/// log2(x / 65536) in Q16, for 0 < x <= 65536.
+fn log2_q16(x: u32) -> i64 {
+    let mut v = x as u64;
+    let mut int = 0i64;
+    while v < 1 << 16 {
+        v <<= 1;
+        int -= 1;
+    }
+    let mut frac = 0i64;
+    let mut bit = 1i64 << 15;
+    while bit > 0 {
+        v = (v * v) >> 16;
+        if v >= 2 << 16 {
+            v >>= 1;
+            frac |= bit;
+        }
+        bit >>= 1;
+    }
+    (int << 16) + frac
+}
+
+/// 2^(y / 65536) in Q16, for y <= 0.
+fn exp2_q16(y: i64) -> u32 {
+    let m = -y.min(0);
+    let n = (m + 0xFFFF) >> 16;
+    if n >= 32 {
+        return 0;
+    }
+    let f = ((n << 16) - m) as u64;
+    let mut acc = 1u64 << 16;
+    for (k, factor) in EXP2_FRACTIONS.iter().enumerate() {
+        if f & (1 << (15 - k)) != 0 {
+            acc = (acc * factor) >> 16;
+        }
+    }
+    (acc >> n) as u32
+}
+
+/// Per-channel lookup table.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Lut {
+    pub r: [u8; 256],
+    pub g: [u8; 256],
+    pub b: [u8; 256],
+}
+
+impl Lut {
+    pub fn build(gamma_pct: u32, kelvin: u32) -> Self {
+        let gamma_pct = gamma_pct.clamp(GAMMA_MIN, GAMMA_MAX);
+        // Output = input^(1/gamma), so values above 100% brighten mid-tones.
+        let exponent = (100i64 << 16) / gamma_pct as i64;
+        let (gr, gg, gb) = whitepoint(kelvin);
+        let mut lut = Lut {
+            r: [0; 256],
+            g: [0; 256],
+            b: [0; 256],
+        };
+        for i in 1..256usize {
+            let x = (i as u32 * 65536) / 255;
+            let level = if gamma_pct == 100 {
+                x as u64
+            } else {
+                exp2_q16((exponent * log2_q16(x)) >> 16) as u64
+            };
+            let channel = |gain: u32| ((level * gain as u64 * 255 / 1000 + 32768) >> 16).min(255) as u8;
+            lut.r[i] = channel(gr);
+            lut.g[i] = channel(gg);
+            lut.b[i] = channel(gb);
+        }
+        lut
+    }
+
+    pub fn is_identity(&self) -> bool {
+        (0..256).all(|i| self.r[i] as usize == i && self.g[i] as usize == i && self.b[i] as usize == i)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NightMode {
+    Off,
+    On,
+    Schedule,
+}
+
+impl NightMode {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "off" => Some(NightMode::Off),
+            "on" => Some(NightMode::On),
+            "schedule" => Some(NightMode::Schedule),
+            _ => None,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            NightMode::Off => "off",
+            NightMode::On => "on",
+            NightMode::Schedule => "schedule",
+        }
+    }
+
+    /// Off -> schedule -> on -> off, for the Settings toggle.
+    pub const fn next(self) -> Self {
+        match self {
+            NightMode::Off => NightMode::Schedule,
+            NightMode::Schedule => NightMode::On,
+            NightMode::On => NightMode::Off,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Backend {
+    Auto,
+    Hardware,
+    Software,
+}
+
+impl Backend {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "auto" => Some(Backend::Auto),
+            "hw" => Some(Backend::Hardware),
+            "sw" => Some(Backend::Software),
+            _ => None,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Backend::Auto => "auto",
+            Backend::Hardware => "hw",
+            Backend::Software => "sw",
+        }
+    }
+}
+
+/// "HH:MM" as minutes after midnight.
+pub fn parse_hhmm(text: &str) -> Option<u32> {
+    let (h, m) = text.trim().split_once(':')?;
+    let (h, m) = (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?);
+    (h < 24 && m < 60).then_some(h * 60 + m)
+}
+
+pub fn format_hhmm(minutes: u32) -> String {
+    alloc::format!("{:02}:{:02}", minutes / 60 % 24, minutes % 60)
+}
+
+/// Whether `minute` (after local midnight) falls in [from, to), wrapping
+/// past midnight when `to` is earlier than `from`.
+pub fn in_window(from: u32, to: u32, minute: u32) -> bool {
+    if from == to {
+        return false;
+    }
+    if from < to {
+        minute >= from && minute < to
+    } else {
+        minute >= from || minute < to
+    }
+}
+
+fn local_minute() -> u32 {
+    let utc_s = crate::timer::wall_clock_unix_millis().div_euclid(1000);
+    let local_s = utc_s + crate::timer::wall_clock_timezone_offset_minutes() as i64 * 60;
+    (local_s.rem_euclid(86_400) / 60) as u32
+}
+
+struct State {
+    gamma_pct: u32,
+    temperature: u32,
+    backend: Backend,
+    night: NightMode,
+    night_temperature: u32,
+    night_from: u32,
+    night_to: u32,
+    /// (gamma, temperature) last pushed to the output.
+    applied: Option<(u32, u32)>,
+    hardware: bool,
+    last_service_ms: u64,
+    dirty: bool,
+}
+
+static STATE: SpinLock<State> = SpinLock::new(State {
+    gamma_pct: 100,
+    temperature: NEUTRAL_TEMPERATURE,
+    backend: Backend::Auto,
+    night: NightMode::Off,
+    night_temperature: DEFAULT_NIGHT_TEMPERATURE,
+    night_from: DEFAULT_NIGHT_FROM,
+    night_to: DEFAULT_NIGHT_TO,
+    applied: None,
+    hardware: false,
+    last_service_ms: 0,
+    dirty: true,
+});
+
+impl State {
+    fn night_active(&self, minute: u32) -> bool {
+        match self.night {
+            NightMode::Off => false,
+            NightMode::On => true,
+            NightMode::Schedule => in_window(self.night_from, self.night_to, minute),
+        }
+    }
+
+    fn effective(&self, minute: u32) -> (u32, u32) {
+        let temperature = if self.night_active(minute) {
+            self.temperature.min(self.night_temperature)
+        } else {
+            self.temperature
+        };
+        (self.gamma_pct, temperature)
+    }
+}
+
+fn config_range(value: Option<&ConfigValue>, default: u32, min: u32, max: u32) -> u32 {
+    match value {
+        Some(ConfigValue::Int(n)) => (*n).clamp(min as i64, max as i64) as u32,
+        _ => default,
+    }
+}
+
+fn on_display_changed(key: &str, value: Option<&ConfigValue>) {
+    let text = match value {
+        Some(ConfigValue::Str(text)) => Some(text.as_str()),
+        _ => None,
+    };
+    let mut st = STATE.lock();
+    match key {
+        GAMMA_KEY => st.gamma_pct = config_range(value, 100, GAMMA_MIN, GAMMA_MAX),
+        TEMPERATURE_KEY => {
+            st.temperature = config_range(value, NEUTRAL_TEMPERATURE, TEMPERATURE_MIN, NEUTRAL_TEMPERATURE)
+        }
+        BACKEND_KEY => st.backend = text.and_then(Backend::parse).unwrap_or(Backend::Auto),
+        NIGHT_KEY => st.night = text.and_then(NightMode::parse).unwrap_or(NightMode::Off),
+        NIGHT_TEMPERATURE_KEY => {
+            st.night_temperature = config_range(value, DEFAULT_NIGHT_TEMPERATURE, TEMPERATURE_MIN, NEUTRAL_TEMPERATURE)
+        }
+        NIGHT_FROM_KEY => st.night_from = text.and_then(parse_hhmm).unwrap_or(DEFAULT_NIGHT_FROM),
+        NIGHT_TO_KEY => st.night_to = text.and_then(parse_hhmm).unwrap_or(DEFAULT_NIGHT_TO),
+        _ => return,
+    }
+    st.dirty = true;
+}
+
+const KEYS: [&str; 7] = [
+    GAMMA_KEY,
+    TEMPERATURE_KEY,
+    BACKEND_KEY,
+    NIGHT_KEY,
+    NIGHT_TEMPERATURE_KEY,
+    NIGHT_FROM_KEY,
+    NIGHT_TO_KEY,
+];
+
+/// Follow the `display.*` color keys from now on.
+pub fn init() {
+    crate::config::watch("display.", on_display_changed);
+}
+
+pub fn load_config() {
+    for key in KEYS {
+        on_display_changed(key, crate::config::get(key).as_ref());
+    }
+}
+
+/// Push `lut` (or no adjustment) to the output. Returns whether the
+/// hardware palette took it.
+fn apply(lut: Option<&Lut>, backend: Backend) -> bool {
+    let hardware = match (backend, lut) {
+        (Backend::Software, _) => {
+            crate::intel_xe::clear_gamma_lut();
+            false
+        }
+        (_, Some(lut)) => crate::intel_xe::set_gamma_lut(&lut.r, &lut.g, &lut.b),
+        (_, None) => crate::intel_xe::clear_gamma_lut(),
+    };
+    if hardware || backend == Backend::Hardware {
+        crate::framebuffer::set_color_lut(None);
+    } else {
+        crate::framebuffer::set_color_lut(lut.map(|lut| (&lut.r, &lut.g, &lut.b)));
+    }
+    hardware
+}
+
+/// Re-evaluate the schedule and settings; returns true when the output
+/// changed and the desktop should present a new frame.
+pub fn service() -> bool {
+    let now = crate::timer::now_ms();
+    let (target, backend) = {
+        let mut st = STATE.lock();
+        if !st.dirty && now.saturating_sub(st.last_service_ms) < SERVICE_INTERVAL_MS {
+            return false;
+        }
+        st.last_service_ms = now;
+        let target = st.effective(local_minute());
+        if !st.dirty && st.applied == Some(target) {
+            return false;
+        }
+        st.dirty = false;
+        st.applied = Some(target);
+        (target, st.backend)
+    };
+    let lut = Lut::build(target.0, target.1);
+    let hardware = apply((!lut.is_identity()).then_some(&lut), backend);
+    STATE.lock().hardware = hardware;
+    crate::klog::log(
+        crate::klog::Level::Info,
+        alloc::format!(
+            "gamma: {}.{:02} {} K ({})",
+            target.0 / 100,
+            target.0 % 100,
+            target.1,
+            if hardware { "hw" } else { "sw" }
+        )
+        .as_str(),
+    );
+    true
+}
+
+/// (gamma %, configured temperature, night mode, night active now) for the
+/// Settings window.
+pub fn settings_snapshot() -> (u32, u32, NightMode, bool) {
+    let st = STATE.lock();
+    (st.gamma_pct, st.temperature, st.night, st.night_active(local_minute()))
+}
+
+pub fn night_window() -> (u32, u32) {
+    let st = STATE.lock();
+    (st.night_from, st.night_to)
+}
+
+fn set_int(key: &str, value: u32) -> Result<(), &'static str> {
+    crate::config::set(key, ConfigValue::Int(value as i64))
+}
+
+pub fn set_gamma(gamma_pct: u32) -> Result<(), &'static str> {
+    set_int(GAMMA_KEY, gamma_pct.clamp(GAMMA_MIN, GAMMA_MAX))
+}
+
+pub fn set_temperature(kelvin: u32) -> Result<(), &'static str> {
+    set_int(TEMPERATURE_KEY, kelvin.clamp(TEMPERATURE_MIN, NEUTRAL_TEMPERATURE))
+}
+
+pub fn set_night_mode(mode: NightMode) -> Result<(), &'static str> {
+    crate::config::set(NIGHT_KEY, ConfigValue::Str(String::from(mode.name())))
+}
+
+/// "1.20" or "120" (percent) as a gamma percentage.
+fn parse_gamma(text: &str) -> Option<u32> {
+    let value = match text.split_once('.') {
+        Some((int, frac)) => {
+            let int = int.parse::<u32>().ok()?;
+            let mut digits = frac.bytes().take(2).map(|b| b.wrapping_sub(b'0') as u32);
+            let tenths = digits.next().filter(|d| *d < 10)?;
+            let hundredths = digits.next().filter(|d| *d < 10).unwrap_or(0);
+            int * 100 + tenths * 10 + hundredths
+        }
+        None => {
+            let n = text.parse::<u32>().ok()?;
+            if n < 10 {
+                n * 100
+            } else {
+                n
+            }
+        }
+    };
+    (GAMMA_MIN..=GAMMA_MAX).contains(&value).then_some(value)
+}
+
+pub fn status_lines() -> Vec<String> {
+    let minute = local_minute();
+    let st = STATE.lock();
+    let (gamma, temperature) = st.effective(minute);
+    let mut out = Vec::new();
+    out.push(alloc::format!(
+        "Salida fb0: gamma {}.{:02}, temperatura {} K, via {}",
+        gamma / 100,
+        gamma % 100,
+        temperature,
+        if st.applied.is_none() {
+            "pendiente"
+        } else if st.hardware {
+            "paleta Intel Xe"
+        } else if crate::framebuffer::backbuffer_enabled() {
+            "sombreado por software"
+        } else {
+            "ninguna (sin backbuffer)"
+        }
+    ));
+    out.push(alloc::format!(
+        "Luz nocturna: {} ({} K, {}-{}){}",
+        st.night.name(),
+        st.night_temperature,
+        format_hhmm(st.night_from),
+        format_hhmm(st.night_to),
+        if st.night_active(minute) { ", activa" } else { "" }
+    ));
+    out.push(alloc::format!("Backend: {}", st.backend.name()));
+    out
+}
+
+/// Shared implementation of `gamma`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim().to_ascii_lowercase();
+    let mut parts = args.split_whitespace();
+    let verb = parts.next().unwrap_or("");
+    let rest: Vec<&str> = parts.collect();
+    let mut out = Vec::new();
+    let result = match (verb, rest.as_slice()) {
+        ("" | "status", []) => {
+            out.extend(status_lines());
+            return out;
+        }
+        ("reset", []) => set_gamma(100).and_then(|_| set_temperature(NEUTRAL_TEMPERATURE)),
+        ("temp" | "temperature", [kelvin]) => match kelvin.trim_end_matches('k').parse::<u32>() {
+            Ok(kelvin) if (TEMPERATURE_MIN..=NEUTRAL_TEMPERATURE).contains(&kelvin) => set_temperature(kelvin),
+            _ => Err("temperatura fuera de rango (1000..6500)"),
+        },
+        ("night", [mode]) => match NightMode::parse(mode) {
+            Some(mode) => set_night_mode(mode),
+            None => Err("modo desconocido (off|on|schedule)"),
+        },
+        ("night", ["schedule", from, to]) => match (parse_hhmm(from), parse_hhmm(to)) {
+            (Some(_), Some(_)) => crate::config::set(NIGHT_FROM_KEY, ConfigValue::Str(String::from(*from)))
+                .and_then(|_| crate::config::set(NIGHT_TO_KEY, ConfigValue::Str(String::from(*to))))
+                .and_then(|_| set_night_mode(NightMode::Schedule)),
+            _ => Err("horas invalidas (HH:MM)"),
+        },
+        ("night", ["temp", kelvin]) => match kelvin.trim_end_matches('k').parse::<u32>() {
+            Ok(kelvin) if (TEMPERATURE_MIN..=NEUTRAL_TEMPERATURE).contains(&kelvin) => {
+                set_int(NIGHT_TEMPERATURE_KEY, kelvin)
+            }
+            _ => Err("temperatura fuera de rango (1000..6500)"),
+        },
+        ("backend", [backend]) => match Backend::parse(backend) {
+            Some(backend) => crate::config::set(BACKEND_KEY, ConfigValue::Str(String::from(backend.name()))),
+            None => Err("backend desconocido (auto|hw|sw)"),
+        },
+        (value, []) => match parse_gamma(value) {
+            Some(gamma) => set_gamma(gamma),
+            None => Err(USAGE),
+        },
+        _ => Err(USAGE),
+    };
+    match result {
+        Ok(()) => out.extend(status_lines()),
+        Err(e) => out.push(alloc::format!("gamma: {}", e)),
+    }
+    out
+}
+
+const USAGE: &str =
+    "uso: gamma [<0.5-3.0>|temp <K>|night off|on|schedule [HH:MM HH:MM]|night temp <K>|backend auto|hw|sw|reset]";
+
+crate::selftest::kernel_tests! {
+    "gamma";
+
+    fn lut_follows_gamma_and_whitepoint() {
+        crate::selftest::ensure(Lut::build(100, NEUTRAL_TEMPERATURE).is_identity(), "identidad")?;
+        let bright = Lut::build(200, NEUTRAL_TEMPERATURE);
+        crate::selftest::ensure((180..=182).contains(&bright.g[128]), "gamma 2.0 en 128")?;
+        crate::selftest::ensure_eq(bright.g[255], 255, "blanco se conserva")?;
+        let warm = Lut::build(100, 3000);
+        crate::selftest::ensure_eq((warm.r[255], warm.b[255]), (255, 112), "3000 K")?;
+        crate::selftest::ensure_eq(whitepoint(3250), (1000, 727, 501), "interpolado")
+    }
+
+    fn schedule_wraps_midnight() {
+        crate::selftest::ensure(in_window(21 * 60, 7 * 60, 23 * 60), "noche")?;
+        crate::selftest::ensure(in_window(21 * 60, 7 * 60, 60), "madrugada")?;
+        crate::selftest::ensure(!in_window(21 * 60, 7 * 60, 12 * 60), "mediodia")?;
+        crate::selftest::ensure(in_window(8 * 60, 9 * 60, 8 * 60 + 30), "ventana diurna")?;
+        crate::selftest::ensure_eq(parse_hhmm("07:30"), Some(450), "hh:mm")?;
+        crate::selftest::ensure_eq(parse_hhmm("24:00"), None, "hora invalida")?;
+        crate::selftest::ensure_eq(parse_gamma("1.2"), Some(120), "decimal")?;
+        crate::selftest::ensure_eq(parse_gamma("2"), Some(200), "entero")
+    }
+}
@@ -0,0 +1,55 @@
+//! Text caret blink, driven by a periodic timer-wheel timer.
+//!
+//! The timer only flips the phase; the compositor picks the change up in
+//! `service_background_tasks` and redraws the focused terminal. Typing
+//! restarts the period so the caret stays solid while keys come in.
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use crate::timer::{self, TimerId};
+
+/// Half period of the blink, the usual desktop default.
+pub const BLINK_MS: u64 = 530;
+
+static VISIBLE: AtomicBool = AtomicBool::new(true);
+static CHANGED: AtomicBool = AtomicBool::new(false);
+static TIMER: AtomicU64 = AtomicU64::new(0);
+
+fn toggle(_: usize) {
+    VISIBLE.fetch_xor(true, Ordering::SeqCst);
+    CHANGED.store(true, Ordering::SeqCst);
+}
+
+fn restart() {
+    let id = timer::every_ms(BLINK_MS, toggle, 0);
+    if let Some(old) = TimerId::from_raw(TIMER.swap(id.raw(), Ordering::SeqCst)) {
+        timer::cancel(old);
+    }
+}
+
+/// Start blinking; called once when the desktop comes up.
+pub fn start() {
+    if TIMER.load(Ordering::SeqCst) == 0 {
+        restart();
+    }
+}
+
+pub fn visible() -> bool {
+    VISIBLE.load(Ordering::SeqCst)
+}
+
+/// Whether the phase flipped since the last call.
+pub fn take_changed() -> bool {
+    CHANGED.swap(false, Ordering::SeqCst)
+}
+
+/// Show the caret now and count the next blink from here.
+pub fn reset() {
+    if TIMER.load(Ordering::SeqCst) == 0 {
+        return;
+    }
+    if !VISIBLE.swap(true, Ordering::SeqCst) {
+        CHANGED.store(true, Ordering::SeqCst);
+    }
+    restart();
+}
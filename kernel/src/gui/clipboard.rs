@@ -0,0 +1,128 @@
+//! Desktop clipboard and the payload types it shares with drag and drop.
+//!
+//! The explorer keeps its own copy/cut state for pasting, but publishes the
+//! same selection here as `Payload::Files`, so any window can read what was
+//! last copied or dropped on it with one type. Linux X11 apps share it through
+//! the CLIPBOARD selection of the compat X server (`syscall.rs`), which watches
+//! `revision` to know when a native copy replaced theirs.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::spinlock::SpinLock;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PayloadKind {
+    Text,
+    Files,
+}
+
+impl PayloadKind {
+    pub const fn bit(self) -> u8 {
+        match self {
+            PayloadKind::Text => 1 << 0,
+            PayloadKind::Files => 1 << 1,
+        }
+    }
+}
+
+/// A file or directory on a FAT volume, located the same way the explorer
+/// clipboard does it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FileRef {
+    pub device_index: Option<usize>,
+    pub dir_cluster: u32,
+    pub dir_path: String,
+    pub cluster: u32,
+    /// Bytes; 0 for directories or when the source did not know it.
+    pub size: u32,
+    pub is_directory: bool,
+    pub name: String,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Payload {
+    Text(String),
+    Files(Vec<FileRef>),
+}
+
+impl Payload {
+    pub fn kind(&self) -> PayloadKind {
+        match self {
+            Payload::Text(_) => PayloadKind::Text,
+            Payload::Files(_) => PayloadKind::Files,
+        }
+    }
+
+    /// Short description for drag badges and status lines.
+    pub fn summary(&self) -> String {
+        match self {
+            Payload::Text(text) => alloc::format!("texto ({} bytes)", text.len()),
+            Payload::Files(files) if files.len() == 1 => files[0].name.clone(),
+            Payload::Files(files) => alloc::format!("{} elementos", files.len()),
+        }
+    }
+
+    /// Plain-text form: the text itself, or the file names separated by spaces.
+    pub fn as_text(&self) -> String {
+        match self {
+            Payload::Text(text) => text.clone(),
+            Payload::Files(files) => {
+                let mut out = String::new();
+                for file in files.iter() {
+                    if !out.is_empty() {
+                        out.push(' ');
+                    }
+                    out.push_str(file.name.as_str());
+                }
+                out
+            }
+        }
+    }
+}
+
+static CLIPBOARD: SpinLock<Option<Payload>> = SpinLock::new(None);
+/// Bumped on every change.
+static REVISION: AtomicU64 = AtomicU64::new(0);
+
+pub fn set(payload: Payload) {
+    *CLIPBOARD.lock() = Some(payload);
+    REVISION.fetch_add(1, Ordering::AcqRel);
+}
+
+pub fn get() -> Option<Payload> {
+    CLIPBOARD.lock().clone()
+}
+
+pub fn clear() {
+    *CLIPBOARD.lock() = None;
+    REVISION.fetch_add(1, Ordering::AcqRel);
+}
+
+pub fn revision() -> u64 {
+    REVISION.load(Ordering::Acquire)
+}
+
+crate::selftest::kernel_tests! {
+    "clipboard";
+
+    fn payload_text_and_summary() {
+        let file = |name: &str| FileRef {
+            device_index: None,
+            dir_cluster: 2,
+            dir_path: String::from("/"),
+            cluster: 9,
+            size: 10,
+            is_directory: false,
+            name: String::from(name),
+        };
+        let one = Payload::Files(alloc::vec![file("A.TXT")]);
+        let two = Payload::Files(alloc::vec![file("A.TXT"), file("B.TXT")]);
+        crate::selftest::ensure_eq(one.summary().as_str(), "A.TXT", "un archivo")?;
+        crate::selftest::ensure_eq(two.summary().as_str(), "2 elementos", "varios")?;
+        crate::selftest::ensure_eq(two.as_text().as_str(), "A.TXT B.TXT", "como texto")?;
+        crate::selftest::ensure_eq(Payload::Text(String::from("hola")).kind(), PayloadKind::Text, "tipo")?;
+        crate::selftest::ensure(PayloadKind::Text.bit() & PayloadKind::Files.bit() == 0, "bits distintos")
+    }
+}
@@ -12,11 +12,17 @@ use sha2::{Digest, Sha256};
 use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 use super::window::{
-    ExplorerItem, ExplorerItemKind, ExplorerSearchClickAction, IdeStudioClickAction,
+    BootEntriesClickAction, ExplorerItem, ExplorerItemKind, ExplorerSearchClickAction, IdeStudioClickAction,
     NotepadClickAction, PreviewElement, PreviewElementKind, SearchClickAction, SearchResultEntry,
-    TaskManagerClickAction, Window, WindowKind, WindowState, WINDOW_RESIZE_GRIP, WINDOW_TITLE_BAR_H,
+    MailClickAction, MailView, PdfViewerClickAction, TaskManagerClickAction, TaskManagerTarget, Window, WindowKind, WindowState, WINDOW_RESIZE_GRIP,
+    WINDOW_TITLE_BAR_H,
 };
 use super::widgets::{taskbar::Taskbar, Widget};
+use super::clipboard::{FileRef, Payload, PayloadKind};
+use super::interaction::{ClickTracker, ContextMenu, DragEvent, DragPhase, DragSession, DragStep};
+use super::dialog::{Dialog, DialogOutcome, FileChoice, FileDialogMode, FileEntry};
+use super::search_overlay::{QuickSearch, QuickSearchOutcome};
+use super::session::{Mode as SessionMode, SavedWindow, State as SessionState};
 use super::{Color, Event, Point, Rect, SpecialKey};
 use crate::framebuffer;
 use crate::fs::FileSystem;
@@ -31,7 +37,7 @@ const START_MENU_ITEMS: usize = 11;
 
 const TOOLS_MENU_W: u32 = 180;
 const TOOLS_MENU_PADDING: i32 = 8;
-const TOOLS_MENU_ITEMS: usize = 5;
+const TOOLS_MENU_ITEMS: usize = 7;
 const GAMES_MENU_W: u32 = 180;
 const GAMES_MENU_PADDING: i32 = 8;
 const GAMES_MENU_ITEMS: usize = 1;
@@ -39,11 +45,23 @@ const APPS_MENU_W: u32 = 220;
 const APPS_MENU_PADDING: i32 = 8;
 const APPS_MENU_MAX_ITEMS: usize = 10;
 const SEARCH_RESULTS_MAX: usize = 48;
+const FETCH_MAX_REDIRECTS: usize = 5;
 const SEARCH_SCAN_MAX_ITEMS: usize = 4096;
 const SEARCH_SCAN_MAX_DEPTH: u8 = 6;
 
-const DOUBLE_CLICK_MIN_TICKS: u64 = 3;
-const DOUBLE_CLICK_TICKS: u64 = 400;
+const DESKTOP_BACKGROUND_KEY: &str = "desktop.background";
+const DESKTOP_TASKBAR_KEY: &str = "desktop.taskbar";
+const DEFAULT_DESKTOP_BACKGROUND: u32 = 0x021F3F;
+const DEFAULT_TASKBAR_BACKGROUND: u32 = 0x222222;
+
+/// Set by the config watcher; the compositor re-reads its theme keys on the
+/// next background pass.
+static DESKTOP_THEME_CHANGED: AtomicBool = AtomicBool::new(false);
+
+fn on_desktop_config_changed(_key: &str, _value: Option<&crate::config::ConfigValue>) {
+    DESKTOP_THEME_CHANGED.store(true, Ordering::Release);
+}
+
 const WINDOW_MIN_FALLBACK_W: u32 = 360;
 const WINDOW_MIN_FALLBACK_H: u32 = 240;
 const NOTEPAD_MAX_TEXT_BYTES: usize = 32 * 1024;
@@ -187,6 +205,7 @@ const COPY_BUFFERED_FALLBACK_MAX_BYTES: usize = 64 * 1024 * 1024;
 const COPY_ENABLE_AP_STORAGE_TASKS: bool = false;
 const APP_RUNNER_MAX_LAYOUT_BYTES: usize = 64 * 1024;
 const IMAGE_VIEWER_MAX_FILE_BYTES: usize = 8 * 1024 * 1024;
+const PDF_VIEWER_MAX_FILE_BYTES: usize = 32 * 1024 * 1024;
 const IMAGE_VIEWER_MAX_INFLATED_BYTES: usize = 32 * 1024 * 1024;
 const IMAGE_VIEWER_MAX_PIXELS: usize = 4_000_000;
 const DESKTOP_DISK_ICON_W: u32 = 112;
@@ -251,8 +270,8 @@ const WEB_PROXY_DEFAULT_BASE: &str = "auto";
 const WEB_PROXY_FALLBACK_BASE: &str = "http://10.0.2.2:37810";
 const WEB_PROXY_DEFAULT_PORT: u16 = 37810;
 const WEB_PROXY_ALT_PORT: u16 = 37820;
-const WEB_PROXY_PROBE_TIMEOUT_TICKS: u64 = 250;
-const WEB_PROXY_FRAME_TIMEOUT_TICKS: u64 = 1200;
+const WEB_PROXY_PROBE_TIMEOUT_MS: u64 = 2_500;
+const WEB_PROXY_FRAME_TIMEOUT_MS: u64 = 12_000;
 const WEB_CEF_FRAME_MAX_PIXELS: usize = 1024 * 1024;
 const WEB_CEF_BRIDGE_ENABLED: bool = true;
 const WEB_LITEHTMLRT_DEFAULT_TARGET: &str = "/LHTMLRT/LHRT0001.BIN";
@@ -265,15 +284,19 @@ const WEB_LITEHTMLRT_STEP_BUDGET: usize = 2;
 const IDE_RUNTIME_RESTART_OP_ID: &str = "__RDX_RUNTIME_RESTART__";
 const IDE_RUNTIME_DELAY_OP_ID: &str = "__RDX_RUNTIME_DELAY_MS__";
 const IDE_RUNTIME_SET_VIEW_OP_ID: &str = "__RDX_RUNTIME_SET_VIEW__";
+const IDE_RUNTIME_SOUND_OP_ID: &str = "__RDX_RUNTIME_SOUND__";
 const IDE_RUNTIME_DEFAULT_STEP_DELAY_MS: u64 = 0;
 const IDE_RUNTIME_DELAY_MAX_MS: u64 = 10_000;
 const IDE_RUNTIME_DELAY_MAX_STEPS: usize = 128;
 const IDE_RUST_JSON_MAX_CHARS: usize = 4096;
+/// Error of a `json_get` whose answer is still on the way; the script runs
+/// again once it is in.
+const IDE_RUST_JSON_PENDING: &str = "json_get pendiente";
 const IDE_LOAD_MAX_SOURCE_BYTES: usize = 2 * 1024 * 1024;
 const MAX_VIRTUAL_DESKTOPS: usize = 10;
 const TASKBAR_DESKTOP_ADD_W: i32 = 24;
 const TASKBAR_DESKTOP_ADD_H: i32 = 24;
-const TASKBAR_DESKTOP_INDICATOR_W: i32 = 68;
+const TASKBAR_WORKSPACE_CELL_W: i32 = 20;
 const TASKBAR_DESKTOP_INDICATOR_H: i32 = 24;
 const DESKTOP_SWITCHER_CARD_W: u32 = 84;
 const DESKTOP_SWITCHER_CARD_H: u32 = 62;
@@ -318,6 +341,13 @@ const CLOCK_PANEL_BUTTON_H: u32 = 20;
 const CLOCK_PANEL_MIN_YEAR: i32 = 1970;
 const CLOCK_PANEL_MAX_YEAR: i32 = 2099;
 const DEFAULT_CLOCK_TZ_OFFSET_MINUTES: i32 = -360;
+const NOTIFY_PANEL_W: u32 = 340;
+const NOTIFY_PANEL_H: u32 = 372;
+const NOTIFY_PANEL_ROW_H: i32 = 46;
+const NOTIFY_PANEL_ROWS: usize = 6;
+const NOTIFY_TOAST_W: u32 = 300;
+const NOTIFY_TOAST_H: u32 = 54;
+const NOTIFY_TOAST_GAP: i32 = 8;
 
 #[derive(Clone, Copy)]
 struct ClockDateTime {
@@ -352,7 +382,7 @@ struct ExplorerClickState {
     kind: ExplorerItemKind,
     cluster: u32,
     label: String,
-    tick: u64,
+    at_us: u64,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -462,7 +492,7 @@ struct DesktopDiskIcon {
     removable: bool,
     is_boot: bool,
     menu_open: bool,
-    last_click_tick: u64,
+    last_click_us: u64,
     rect: Rect,
 }
 
@@ -555,8 +585,16 @@ struct DesktopDragState {
     open_on_release: bool,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DesktopPromptKind {
+    CreateFolder,
+    /// `ftp open <url>` in a terminal whose directory is `dir_cluster`.
+    ConnectServer,
+}
+
 #[derive(Clone)]
 struct DesktopCreateFolderState {
+    kind: DesktopPromptKind,
     dir_cluster: u32,
     dir_path: String,
     device_index: Option<usize>,
@@ -674,6 +712,8 @@ struct TerminalFsTask {
     win_id: usize,
     priority: SystemTaskPriority,
     kind: TerminalFsTaskKind,
+    /// Who queued it; the copy runs with their permissions.
+    cred: crate::perm::Cred,
 }
 
 #[derive(Clone)]
@@ -971,6 +1011,31 @@ struct NotepadSavePromptState {
     scroll_top: usize,
 }
 
+/// What the answer of the open modal dialog is for.
+enum DialogPurpose {
+    /// Message with nothing to do afterwards.
+    Notice,
+    NotepadOpen(usize),
+    NotepadDelete(usize),
+    BrowserDownload { win_id: usize, url: String },
+    SaveScreenshot(Vec<u8>),
+    /// Print the notepad or browser window.
+    Print(usize),
+    /// "Replace?" for a save target that already exists.
+    ConfirmReplace(Box<DialogPurpose>, FileChoice),
+    /// The boot-time offer to reopen the last session.
+    RestoreSession(Vec<SavedWindow>),
+    /// "Mount?" for a USB volume plugged in after boot (its block handle).
+    MountUsb(usize),
+    /// "Authorize?" for a device tunneled over Thunderbolt/USB4.
+    AuthorizeThunderbolt(crate::thunderbolt::Port),
+}
+
+struct ModalDialog {
+    dialog: Dialog,
+    purpose: DialogPurpose,
+}
+
 #[derive(Clone, Copy)]
 struct WindowMoveCapture {
     win_id: usize,
@@ -1362,6 +1427,8 @@ struct LinuxRunLoopContainer {
     dependency_entries: Vec<crate::fs::DirEntry>,
     manifest_map: Vec<(String, String, String)>,
     plan: Option<crate::linux_compat::LinuxDynLaunchPlan>,
+    /// Entry in `procenv` holding cwd, streams and exit status.
+    proc_id: crate::procenv::ProcId,
     last_note: String,
     error: String,
 }
@@ -1435,6 +1502,7 @@ impl LinuxRunLoopContainer {
             dependency_entries: Vec::new(),
             manifest_map: Vec::new(),
             plan: None,
+            proc_id: 0,
             last_note: String::from("inicializado"),
             error: String::new(),
         }
@@ -1469,6 +1537,83 @@ impl LinuxRunLoopContainer {
     }
 }
 
+/// A terminal `fetch` whose request runs on the background network worker.
+/// `candidates` are the URLs to try in order (see
+/// `build_fetch_url_candidates`); `index` is the one in flight.
+struct PendingFetch {
+    win_id: usize,
+    request_id: crate::net::worker::RequestId,
+    candidates: Vec<String>,
+    index: usize,
+    file_name: String,
+    dir_cluster: u32,
+    repo_mode: bool,
+}
+
+impl PendingFetch {
+    fn submit(&mut self) -> bool {
+        let request = crate::net::request::HttpRequest::get(self.candidates[self.index].as_str())
+            .redirects(crate::net::request::RedirectPolicy::Follow(FETCH_MAX_REDIRECTS));
+        match crate::net::worker::submit(request) {
+            Some(id) => {
+                self.request_id = id;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A page the browser loads on an executor task; `service_browser_loads`
+/// shows it once the task is done.
+struct BrowserLoad {
+    win_id: usize,
+    url: String,
+    job: crate::executor::JoinHandle<BrowserLoaded>,
+}
+
+/// A finished page load, with the WebKit bridge endpoint that answered.
+struct BrowserLoaded {
+    render: crate::web_servo_bridge::ServoBridgeRender,
+    endpoint: Option<String>,
+}
+
+/// Input for the WebKit bridge, sent on an executor task.
+struct BrowserBridgeInput {
+    win_id: usize,
+    job: crate::executor::JoinHandle<BridgeInputSent>,
+}
+
+struct BridgeInputSent {
+    /// The endpoint that answered, and its reply.
+    reply: Option<(String, String)>,
+    tried: Vec<String>,
+    /// The frame drawn after a `200`.
+    surface: Option<crate::web_servo_bridge::ServoBridgeSurface>,
+}
+
+/// A terminal command waiting on the network. Its lines go to the window
+/// when it is done; `endpoint` is a WebKit bridge base that answered.
+struct TerminalNetJob {
+    win_id: usize,
+    job: crate::executor::JoinHandle<(Vec<String>, Option<String>)>,
+}
+
+/// A `json_get` of an IDE script in flight.
+struct IdeJsonFetch {
+    url: String,
+    job: crate::executor::JoinHandle<Result<String, String>>,
+}
+
+/// An IDE script run that stopped at a `json_get` still in flight.
+struct IdeRunWaiting {
+    scope: IdeRuntimeScope,
+    win_id: usize,
+    source: String,
+    label: String,
+    reset_preview: bool,
+}
+
 struct TerminalStreamQueue {
     win_id: usize,
     lines: VecDeque<String>,
@@ -1502,8 +1647,17 @@ pub struct Compositor {
     minimized_overflow_open: bool,
     minimized_overflow_scroll: usize,
     clock_panel_open: bool,
+    notify_panel_open: bool,
+    notify_panel_scroll: usize,
+    desktop_background: u32,
     last_mouse_down: bool,
     last_mouse_right_down: bool,
+    /// When the event being handled was polled (`perf::now_us`); click
+    /// timing reads this instead of the time it is handled.
+    event_time_us: u64,
+    clicks: ClickTracker,
+    drag_session: Option<DragSession>,
+    window_context_menu: Option<ContextMenu>,
     mouse_input_priority_frames: u8,
     mouse_runloop_throttle_skip: u8,
     taskbar_window: Window,
@@ -1516,6 +1670,8 @@ pub struct Compositor {
     desktop_context_menu: Option<ExplorerContextMenuState>,
     ide_context_menu: Option<IdeContextMenuState>,
     ide_selection_drag: Option<IdeSelectionDragState>,
+    /// Notepad window whose text area is being drag-selected.
+    notepad_selection_drag: Option<usize>,
     ide_text_clipboard: String,
     explorer_clipboard: Option<ExplorerClipboardState>,
     pointer_capture: Option<WindowPointerCapture>,
@@ -1571,6 +1727,15 @@ pub struct Compositor {
     headless_install_units_reported: usize,
     headless_install_items_reported: usize,
     notepad_save_prompt: Option<NotepadSavePromptState>,
+    modal_dialog: Option<ModalDialog>,
+    /// Super+Space search overlay, drawn above every window.
+    quick_search: Option<QuickSearch>,
+    /// Volume-wide `fswatch` watch behind the Explorer auto-refresh, with
+    /// the volume it was opened on.
+    fs_watch: Option<(u64, u32)>,
+    /// Microphone reader that keeps capture running for the level meters
+    /// of open Settings and recorder windows.
+    capture_meter: Option<u32>,
     ide_unsaved_prompt: Option<IdeUnsavedPromptState>,
     ide_post_export_action: Option<IdeUnsavedPromptState>,
     manual_unmount_lock: bool,
@@ -1587,6 +1752,16 @@ pub struct Compositor {
     linux_runloop_snapshot_tick: u64,
     linux_runloop_snapshot_active: bool,
     terminal_stream_queues: Vec<TerminalStreamQueue>,
+    pending_fetches: Vec<PendingFetch>,
+    browser_loads: Vec<BrowserLoad>,
+    browser_bridge_inputs: Vec<BrowserBridgeInput>,
+    terminal_net_jobs: Vec<TerminalNetJob>,
+    ide_json_fetches: Vec<IdeJsonFetch>,
+    /// Answers to `json_get`, kept until a script run has read them.
+    ide_json_results: Vec<(String, Result<String, String>)>,
+    /// URLs whose answer the script run in progress has read.
+    ide_json_used: Vec<String>,
+    ide_runs_waiting: Vec<IdeRunWaiting>,
     terminal_stream_auto_tune: bool,
     terminal_stream_global_budget: usize,
     terminal_stream_per_window_budget: usize,
@@ -1612,6 +1787,8 @@ pub struct Compositor {
     ide_runtime_state: Vec<IdeRuntimeStateSlot>,
     desktop_scroll: i32,
     needs_repaint: bool,
+    /// Running open/minimize/restore animations, at most one per window.
+    animations: Vec<crate::gui::effects::Animation>,
     /// Right-click context menu on a pinned taskbar item: (actual_idx, menu_x, menu_y)
     pinned_context_menu_index: Option<(usize, i32, i32)>,
     is_suspended: bool,
@@ -1812,10 +1989,23 @@ impl Compositor {
             + PINNED_ARROW_W
             + 4
             + SETTINGS_ICON_W
+            + 4
+            + NOTIFY_ICON_W
             + 4;
         Rect::new(clock_x, 0, CLOCK_W as u32, self.taskbar.rect.height)
     }
 
+    fn taskbar_notify_screen_rect(&self) -> Rect {
+        use crate::gui::widgets::taskbar::NOTIFY_ICON_W;
+        let clock = self.taskbar_clock_screen_rect();
+        Rect::new(
+            clock.x - 4 - NOTIFY_ICON_W,
+            clock.y,
+            NOTIFY_ICON_W as u32,
+            clock.height,
+        )
+    }
+
     fn taskbar_clock_screen_rect(&self) -> Rect {
         let rel = self.taskbar_clock_rect();
         Rect::new(
@@ -1941,6 +2131,300 @@ impl Compositor {
         true
     }
 
+    fn toggle_notify_panel(&mut self) {
+        self.notify_panel_open = !self.notify_panel_open;
+        self.notify_panel_scroll = 0;
+        if self.notify_panel_open {
+            crate::gui::notifications::mark_all_read();
+        }
+        self.clock_panel_open = false;
+        self.taskbar.start_menu_open = false;
+        self.start_tools_open = false;
+        self.start_games_open = false;
+        self.start_apps_open = false;
+        self.desktop_switcher_open = false;
+        self.minimized_overflow_open = false;
+    }
+
+    fn notify_panel_rect(&self) -> Rect {
+        let clock = self.taskbar_clock_screen_rect();
+        let preferred_x = clock.x + clock.width as i32 - NOTIFY_PANEL_W as i32;
+        let max_x = self.width.saturating_sub(NOTIFY_PANEL_W as usize + 8) as i32;
+        let x = if max_x >= 8 {
+            preferred_x.clamp(8, max_x)
+        } else {
+            0
+        };
+        let y = (self.taskbar.rect.y - NOTIFY_PANEL_H as i32 - 8).max(8);
+        Rect::new(x, y, NOTIFY_PANEL_W, NOTIFY_PANEL_H)
+    }
+
+    fn notify_panel_row_rect(&self, index: usize) -> Rect {
+        let panel = self.notify_panel_rect();
+        Rect::new(
+            panel.x + 10,
+            panel.y + 34 + index as i32 * NOTIFY_PANEL_ROW_H,
+            panel.width - 20,
+            (NOTIFY_PANEL_ROW_H - 4) as u32,
+        )
+    }
+
+    fn notify_panel_row_close_rect(&self, index: usize) -> Rect {
+        let row = self.notify_panel_row_rect(index);
+        Rect::new(row.x + row.width as i32 - 18, row.y + 4, 14, 14)
+    }
+
+    fn notify_panel_clear_button_rect(&self) -> Rect {
+        let panel = self.notify_panel_rect();
+        Rect::new(panel.x + 12, panel.y + panel.height as i32 - 32, 70, 22)
+    }
+
+    fn notify_panel_close_button_rect(&self) -> Rect {
+        let panel = self.notify_panel_rect();
+        Rect::new(
+            panel.x + panel.width as i32 - 62,
+            panel.y + panel.height as i32 - 32,
+            50,
+            22,
+        )
+    }
+
+    fn handle_notify_panel_click(&mut self, mouse_x: i32, mouse_y: i32) -> bool {
+        if !self.notify_panel_open {
+            return false;
+        }
+        let point = Point { x: mouse_x, y: mouse_y };
+        if self.taskbar_notify_screen_rect().contains(point) || !self.notify_panel_rect().contains(point) {
+            self.notify_panel_open = false;
+            return true;
+        }
+        if self.notify_panel_clear_button_rect().contains(point) {
+            crate::gui::notifications::clear();
+            self.notify_panel_scroll = 0;
+            return true;
+        }
+        if self.notify_panel_close_button_rect().contains(point) {
+            self.notify_panel_open = false;
+            return true;
+        }
+        let items = crate::gui::notifications::history();
+        for idx in 0..NOTIFY_PANEL_ROWS {
+            let Some(item) = items.get(self.notify_panel_scroll + idx) else {
+                break;
+            };
+            if self.notify_panel_row_close_rect(idx).contains(point) {
+                crate::gui::notifications::remove(item.id);
+                let max_scroll = items.len().saturating_sub(1).saturating_sub(NOTIFY_PANEL_ROWS);
+                self.notify_panel_scroll = self.notify_panel_scroll.min(max_scroll);
+                return true;
+            }
+        }
+        true
+    }
+
+    fn scroll_notify_panel(&mut self, wheel_delta: i32) {
+        let total = crate::gui::notifications::history().len();
+        let max_scroll = total.saturating_sub(NOTIFY_PANEL_ROWS);
+        if wheel_delta > 0 {
+            self.notify_panel_scroll = self.notify_panel_scroll.saturating_sub(1);
+        } else {
+            self.notify_panel_scroll = (self.notify_panel_scroll + 1).min(max_scroll);
+        }
+    }
+
+    /// Toast `index` counting up from the taskbar, right-aligned.
+    fn notify_toast_rect(&self, index: usize) -> Rect {
+        let x = (self.width as i32 - NOTIFY_TOAST_W as i32 - 12).max(0);
+        let y = self.taskbar.rect.y
+            - NOTIFY_TOAST_GAP
+            - (index as i32 + 1) * (NOTIFY_TOAST_H as i32 + NOTIFY_TOAST_GAP);
+        Rect::new(x, y.max(0), NOTIFY_TOAST_W, NOTIFY_TOAST_H)
+    }
+
+    fn notify_toast_close_rect(&self, index: usize) -> Rect {
+        let toast = self.notify_toast_rect(index);
+        Rect::new(toast.x + toast.width as i32 - 18, toast.y + 4, 14, 14)
+    }
+
+    /// Close box dismisses a toast; anywhere else on it opens the center.
+    fn handle_notify_toast_click(&mut self, mouse_x: i32, mouse_y: i32) -> bool {
+        if self.notify_panel_open {
+            return false;
+        }
+        let point = Point { x: mouse_x, y: mouse_y };
+        let toasts = crate::gui::notifications::active_toasts();
+        for (idx, toast) in toasts.iter().enumerate() {
+            if self.notify_toast_close_rect(idx).contains(point) {
+                crate::gui::notifications::dismiss_toast(toast.id);
+                return true;
+            }
+            if self.notify_toast_rect(idx).contains(point) {
+                self.toggle_notify_panel();
+                return true;
+            }
+        }
+        false
+    }
+
+    fn notify_time_label(posted_unix_ms: i64) -> String {
+        let offset_minutes = crate::timer::wall_clock_timezone_offset_minutes() as i64;
+        let dt = Self::clock_datetime_from_local_seconds(posted_unix_ms / 1000 + offset_minutes * 60);
+        alloc::format!("{:02}:{:02}", dt.hour, dt.minute)
+    }
+
+    fn draw_notify_frame(rect: Rect, accent: u32) {
+        let x = rect.x.max(0) as usize;
+        let y = rect.y.max(0) as usize;
+        let w = rect.width as usize;
+        let h = rect.height as usize;
+        framebuffer::rect(x, y, w, h, 0x111827);
+        framebuffer::rect(x, y, w, 1, 0x6FA8DC);
+        framebuffer::rect(x, y + h - 1, w, 1, 0x1D4E89);
+        framebuffer::rect(x, y, 1, h, 0x6FA8DC);
+        framebuffer::rect(x + w - 1, y, 1, h, 0x1D4E89);
+        framebuffer::rect(x + 1, y + 1, 3, h.saturating_sub(2), accent);
+    }
+
+    fn draw_notify_close_box(rect: Rect) {
+        framebuffer::rect(
+            rect.x.max(0) as usize,
+            rect.y.max(0) as usize,
+            rect.width as usize,
+            rect.height as usize,
+            0x374151,
+        );
+        framebuffer::draw_text_5x7((rect.x + 4).max(0) as usize, (rect.y + 4).max(0) as usize, "x", 0xFFFFFF);
+    }
+
+    fn draw_notify_toasts_overlay(&mut self) {
+        if self.notify_panel_open {
+            return;
+        }
+        let toasts = crate::gui::notifications::active_toasts();
+        for (idx, toast) in toasts.iter().enumerate() {
+            let rect = self.notify_toast_rect(idx);
+            Self::draw_notify_frame(rect, toast.urgency.color());
+            framebuffer::draw_text_5x7(
+                (rect.x + 12).max(0) as usize,
+                (rect.y + 8).max(0) as usize,
+                Self::trim_ascii_line(toast.title.as_str(), 40).as_str(),
+                0xEAF4FF,
+            );
+            framebuffer::draw_text_5x7(
+                (rect.x + 12).max(0) as usize,
+                (rect.y + 22).max(0) as usize,
+                Self::trim_ascii_line(toast.body.as_str(), 46).as_str(),
+                0xC9D6E3,
+            );
+            framebuffer::draw_text_5x7(
+                (rect.x + 12).max(0) as usize,
+                (rect.y + 38).max(0) as usize,
+                Self::trim_ascii_line(toast.source.as_str(), 24).as_str(),
+                0x7C8FA6,
+            );
+            Self::draw_notify_close_box(self.notify_toast_close_rect(idx));
+        }
+    }
+
+    fn draw_notify_panel_overlay(&mut self) {
+        if !self.notify_panel_open {
+            return;
+        }
+
+        let panel = self.notify_panel_rect();
+        Self::draw_notify_frame(panel, 0x6FA8DC);
+        let items = crate::gui::notifications::history();
+        framebuffer::draw_text_5x7(
+            (panel.x + 12).max(0) as usize,
+            (panel.y + 12).max(0) as usize,
+            alloc::format!("Notificaciones ({})", items.len()).as_str(),
+            0xEAF4FF,
+        );
+
+        if items.is_empty() {
+            let row = self.notify_panel_row_rect(0);
+            framebuffer::draw_text_5x7(
+                (row.x + 8).max(0) as usize,
+                (row.y + 8).max(0) as usize,
+                "Sin notificaciones",
+                0x93A4B8,
+            );
+        }
+        for idx in 0..NOTIFY_PANEL_ROWS {
+            let Some(item) = items.get(self.notify_panel_scroll + idx) else {
+                break;
+            };
+            let row = self.notify_panel_row_rect(idx);
+            framebuffer::rect(
+                row.x.max(0) as usize,
+                row.y.max(0) as usize,
+                row.width as usize,
+                row.height as usize,
+                if idx % 2 == 0 { 0x1F2937 } else { 0x172033 },
+            );
+            framebuffer::rect(row.x.max(0) as usize, row.y.max(0) as usize, 3, row.height as usize, item.urgency.color());
+            framebuffer::draw_text_5x7(
+                (row.x + 10).max(0) as usize,
+                (row.y + 6).max(0) as usize,
+                Self::trim_ascii_line(item.title.as_str(), 40).as_str(),
+                0xF8FAFC,
+            );
+            framebuffer::draw_text_5x7(
+                (row.x + 10).max(0) as usize,
+                (row.y + 18).max(0) as usize,
+                Self::trim_ascii_line(item.body.as_str(), 48).as_str(),
+                0xC9D6E3,
+            );
+            framebuffer::draw_text_5x7(
+                (row.x + 10).max(0) as usize,
+                (row.y + 30).max(0) as usize,
+                alloc::format!(
+                    "{}  {}",
+                    Self::notify_time_label(item.posted_unix_ms),
+                    Self::trim_ascii_line(item.source.as_str(), 24)
+                )
+                .as_str(),
+                0x7C8FA6,
+            );
+            Self::draw_notify_close_box(self.notify_panel_row_close_rect(idx));
+        }
+        if items.len() > NOTIFY_PANEL_ROWS {
+            framebuffer::draw_text_5x7(
+                (panel.x + 96).max(0) as usize,
+                (panel.y + panel.height as i32 - 24).max(0) as usize,
+                alloc::format!(
+                    "{}-{} de {}",
+                    self.notify_panel_scroll + 1,
+                    (self.notify_panel_scroll + NOTIFY_PANEL_ROWS).min(items.len()),
+                    items.len()
+                )
+                .as_str(),
+                0x93A4B8,
+            );
+        }
+
+        let buttons = [
+            (self.notify_panel_clear_button_rect(), "Limpiar", 0x2F4054, 0xEAF4FF),
+            (self.notify_panel_close_button_rect(), "Cerrar", 0x5A2F2F, 0xFFEDED),
+        ];
+        for (rect, label, bg, fg) in buttons.iter() {
+            framebuffer::rect(
+                rect.x.max(0) as usize,
+                rect.y.max(0) as usize,
+                rect.width as usize,
+                rect.height as usize,
+                *bg,
+            );
+            framebuffer::rect(rect.x.max(0) as usize, rect.y.max(0) as usize, rect.width as usize, 1, 0x7C8FA6);
+            framebuffer::draw_text_5x7(
+                (rect.x + 8).max(0) as usize,
+                (rect.y + 8).max(0) as usize,
+                *label,
+                *fg,
+            );
+        }
+    }
+
     fn active_desktop_id(&self) -> u8 {
         (self.active_desktop_index + 1).min(MAX_VIRTUAL_DESKTOPS) as u8
     }
@@ -2131,6 +2615,139 @@ impl Compositor {
         true
     }
 
+    /// Create desktops until `index` exists, staying on the current one.
+    fn ensure_virtual_desktop(&mut self, index: usize) -> bool {
+        if index >= MAX_VIRTUAL_DESKTOPS {
+            return false;
+        }
+        let previous = self.active_desktop_index;
+        while self.virtual_desktops.len() <= index {
+            if !self.create_virtual_desktop() {
+                return false;
+            }
+        }
+        if self.active_desktop_index != previous {
+            let _ = self.switch_to_desktop(previous);
+        }
+        true
+    }
+
+    fn move_window_to_desktop(&mut self, win_id: usize, index: usize) -> bool {
+        if index >= self.virtual_desktops.len() {
+            return false;
+        }
+        let target = (index + 1) as u8;
+        let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) else {
+            return false;
+        };
+        if win.desktop_id == target {
+            return false;
+        }
+        win.desktop_id = target;
+        let title = win.title.clone();
+        for tab in self.minimized_windows.iter_mut().filter(|tab| tab.win_id == win_id) {
+            tab.desktop_id = target;
+        }
+        self.animations.retain(|animation| animation.win_id != win_id);
+        if self.active_window_id == Some(win_id) && index != self.active_desktop_index {
+            self.active_window_id = None;
+        }
+        self.sync_active_window_for_desktop();
+        self.clamp_minimized_overflow_scroll();
+        self.desktop_surface_status = alloc::format!("'{}' movida al escritorio {}.", title, index + 1);
+        self.needs_repaint = true;
+        true
+    }
+
+    /// Super+N goes to workspace N, Super+Shift+N sends the focused window
+    /// there; either creates the missing workspaces up to N.
+    fn handle_workspace_key(&mut self, index: usize, move_window: bool) {
+        if !self.ensure_virtual_desktop(index) {
+            return;
+        }
+        if move_window {
+            if let Some(win_id) = self.active_window_id {
+                let _ = self.move_window_to_desktop(win_id, index);
+            }
+        } else {
+            let _ = self.switch_to_desktop(index);
+        }
+        self.needs_repaint = true;
+    }
+
+    fn desktop_window_count(&self, index: usize) -> usize {
+        let desktop_id = (index + 1) as u8;
+        self.windows.iter().filter(|w| w.desktop_id == desktop_id).count()
+    }
+
+    /// `workspace [n | move <n> [titulo]]` from a terminal.
+    fn workspace_command_lines(&mut self, win_id: usize, args: &str) -> Vec<String> {
+        const USAGE: &str = "Uso: workspace [n] | workspace move <n> [titulo]";
+        let args = args.trim();
+        let parse_index = |text: &str| {
+            text.parse::<usize>()
+                .ok()
+                .filter(|n| (1..=MAX_VIRTUAL_DESKTOPS).contains(n))
+                .map(|n| n - 1)
+        };
+        if let Some(rest) = args.strip_prefix("move") {
+            let rest = rest.trim();
+            let (number, title) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            let Some(index) = parse_index(number) else {
+                return alloc::vec![String::from(USAGE)];
+            };
+            let needle = title.trim().to_ascii_lowercase();
+            let active_desktop = self.active_desktop_id();
+            let target = if needle.is_empty() {
+                Some(win_id)
+            } else {
+                self.windows
+                    .iter()
+                    .rev()
+                    .find(|w| w.desktop_id == active_desktop && w.title.to_ascii_lowercase().contains(needle.as_str()))
+                    .map(|w| w.id)
+            };
+            let Some(target) = target else {
+                return alloc::vec![alloc::format!("workspace: no hay ninguna ventana '{}'", title.trim())];
+            };
+            if !self.ensure_virtual_desktop(index) {
+                return alloc::vec![self.desktop_surface_status.clone()];
+            }
+            if !self.move_window_to_desktop(target, index) {
+                return alloc::vec![alloc::format!("workspace: la ventana ya esta en el escritorio {}", index + 1)];
+            }
+            return alloc::vec![self.desktop_surface_status.clone()];
+        }
+        if !args.is_empty() {
+            let Some(index) = parse_index(args) else {
+                return alloc::vec![String::from(USAGE)];
+            };
+            if !self.ensure_virtual_desktop(index) {
+                return alloc::vec![self.desktop_surface_status.clone()];
+            }
+            let _ = self.switch_to_desktop(index);
+            self.needs_repaint = true;
+        }
+
+        let mut out = Vec::new();
+        for index in 0..self.virtual_desktops.len() {
+            let marker = if index == self.active_desktop_index { '*' } else { ' ' };
+            out.push(alloc::format!(
+                "{} Escritorio {}: {} ventanas",
+                marker,
+                index + 1,
+                self.desktop_window_count(index)
+            ));
+            let desktop_id = (index + 1) as u8;
+            for win in self.windows.iter().filter(|w| w.desktop_id == desktop_id) {
+                let state = if win.state == WindowState::Minimized { " (minimizada)" } else { "" };
+                out.push(alloc::format!("    {}{}", win.title, state));
+            }
+        }
+        out.push(String::from(USAGE));
+        out
+    }
+
     fn desktop_directory_name_for_index(index: usize) -> String {
         if index == 0 {
             String::from("Desktop")
@@ -2317,6 +2934,14 @@ impl Compositor {
             WindowKind::Settings => Some("settings"),
             WindowKind::WifiManager => Some("wifi"),
             WindowKind::TaskManager => Some("taskmgr"),
+            WindowKind::AboutPc => Some("aboutpc"),
+            WindowKind::Mail => Some("mail"),
+            WindowKind::BootEntries => Some("bootvar"),
+            WindowKind::PdfViewer => Some("pdf"),
+            WindowKind::Calculator => Some("calc"),
+            WindowKind::Calendar => Some("calendar"),
+            WindowKind::Clock => Some("clock"),
+            WindowKind::Recorder => Some("recorder"),
             WindowKind::Search
             | WindowKind::Explorer
             | WindowKind::ImageViewer
@@ -2464,7 +3089,7 @@ impl Compositor {
             return Err(String::from("No se pudo montar el volumen de Desktop."));
         }
         let recents_cluster = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             let canonical_path = alloc::format!("{}/", DESKTOP_RECENTS_DIR_NAME);
             let legacy_path = alloc::format!("{}/", DESKTOP_RECENTS_DIR_LEGACY_HASH_NAME);
 
@@ -2575,7 +3200,7 @@ impl Compositor {
         {
             let mut favorites_cluster = legacy_cluster;
             let path = {
-                let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 let canonical_path = alloc::format!("{}/", FAVORITES_DIR_NAME);
                 if create_if_missing {
                     if fat
@@ -2618,7 +3243,7 @@ impl Compositor {
         }
         let (favorites_cluster, _favorites_path) = self.resolve_favorites_directory_target(true)?;
         let result = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             Self::write_shortcut_command_once_in_directory(fat, favorites_cluster, label, command)
                 .map_err(String::from)?
         };
@@ -2752,7 +3377,7 @@ impl Compositor {
 
         // If this is already a .lnk shortcut, preserve its target command.
         if item.label.to_ascii_lowercase().ends_with(".lnk") {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             if let Some(command) = Self::read_shortcut_file_command(fat, item.cluster, item.size) {
                 return Some(command);
             }
@@ -2768,6 +3393,8 @@ impl Compositor {
 
         let kind = if Self::is_png_file_name(item.label.as_str()) {
             "img"
+        } else if Self::is_pdf_file_name(item.label.as_str()) {
+            "pdf"
         } else if Self::is_audio_file_name(item.label.as_str()) {
             "aud"
         } else if Self::is_video_file_name(item.label.as_str()) {
@@ -2807,7 +3434,7 @@ impl Compositor {
         };
 
         let write_result = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             Self::write_shortcut_command_once_in_directory(
                 fat,
                 recents_cluster,
@@ -3041,9 +3668,14 @@ impl Compositor {
         true
     }
 
+    /// One numbered cell per workspace, so the strip grows with them.
+    fn taskbar_desktop_indicator_width(&self) -> i32 {
+        self.virtual_desktops.len().max(1) as i32 * TASKBAR_WORKSPACE_CELL_W + 4
+    }
+
     fn taskbar_desktop_add_rect(&self) -> Rect {
         let tray_x = self.taskbar.tray_start_x();
-        let x = (tray_x - TASKBAR_DESKTOP_INDICATOR_W - TASKBAR_DESKTOP_ADD_W - 12).max(92);
+        let x = (tray_x - self.taskbar_desktop_indicator_width() - TASKBAR_DESKTOP_ADD_W - 12).max(92);
         let y = ((self.taskbar.rect.height as i32 - TASKBAR_DESKTOP_ADD_H) / 2).max(0);
         Rect::new(x, y, TASKBAR_DESKTOP_ADD_W as u32, TASKBAR_DESKTOP_ADD_H as u32)
     }
@@ -3053,11 +3685,26 @@ impl Compositor {
         Rect::new(
             add.x + TASKBAR_DESKTOP_ADD_W + 4,
             add.y,
-            TASKBAR_DESKTOP_INDICATOR_W as u32,
+            self.taskbar_desktop_indicator_width() as u32,
             TASKBAR_DESKTOP_INDICATOR_H as u32,
         )
     }
 
+    fn taskbar_workspace_cell_rect(&self, index: usize) -> Rect {
+        let strip = self.taskbar_desktop_indicator_rect();
+        Rect::new(
+            strip.x + 2 + index as i32 * TASKBAR_WORKSPACE_CELL_W,
+            strip.y + 2,
+            (TASKBAR_WORKSPACE_CELL_W - 2) as u32,
+            (TASKBAR_DESKTOP_INDICATOR_H - 4) as u32,
+        )
+    }
+
+    /// Workspace cell under a taskbar-relative point.
+    fn taskbar_workspace_hit_test(&self, p: Point) -> Option<usize> {
+        (0..self.virtual_desktops.len()).find(|&idx| self.taskbar_workspace_cell_rect(idx).contains(p))
+    }
+
     fn desktop_switcher_rect(&self) -> Rect {
         let rows = (MAX_VIRTUAL_DESKTOPS + DESKTOP_SWITCHER_COLS - 1) / DESKTOP_SWITCHER_COLS;
         let cards_w = DESKTOP_SWITCHER_COLS as i32 * DESKTOP_SWITCHER_CARD_W as i32
@@ -3279,7 +3926,7 @@ impl Compositor {
     }
 
     fn collect_search_app_candidates(&mut self, query_lower: &str, out: &mut Vec<SearchCandidate>) {
-        const BUILTIN_APPS: [(&str, &str); 8] = [
+        const BUILTIN_APPS: [(&str, &str); 14] = [
             ("Notepad", "notepad"),
             ("Redux Studio", "ide"),
             ("Web Browser", "browser"),
@@ -3288,6 +3935,12 @@ impl Compositor {
             ("App Runner", "apprunner"),
             ("CPP-DOOM Launcher", "cppdoom"),
             ("Linux Bridge", "linuxbridge"),
+            ("Calculadora", "calc"),
+            ("Calendario", "calendar"),
+            ("Reloj y alarmas", "clock"),
+            ("Grabadora de voz", "recorder"),
+            ("Administrador de tareas", "taskmgr"),
+            ("Correo", "mail"),
         ];
 
         for (label, command) in BUILTIN_APPS.iter() {
@@ -3320,6 +3973,127 @@ impl Compositor {
         }
     }
 
+    fn search_file_kind(name: &str) -> &'static str {
+        if Self::is_png_file_name(name) {
+            "img"
+        } else if Self::is_pdf_file_name(name) {
+            "pdf"
+        } else if Self::is_audio_file_name(name) {
+            "aud"
+        } else if Self::is_video_file_name(name) {
+            "vid"
+        } else {
+            "txt"
+        }
+    }
+
+    /// Settings pages plus the files and folders of the background index
+    /// (`crate::search_index`), matched by name or by the words in text files.
+    fn collect_search_index_candidates(&mut self, query_lower: &str, out: &mut Vec<SearchCandidate>) {
+        for hit in crate::search_index::search_settings(query_lower, 4) {
+            // "Configuracion" is already listed as an app.
+            if out.iter().any(|c| c.label.eq_ignore_ascii_case(hit.label.as_str())) {
+                continue;
+            }
+            Self::push_search_candidate_unique(
+                out,
+                SearchCandidate {
+                    subtitle: if hit.detail.is_empty() {
+                        String::from("Ajuste")
+                    } else {
+                        alloc::format!("Ajuste ({})", hit.detail)
+                    },
+                    command: Self::recent_app_command(hit.app),
+                    label: hit.label,
+                    score: hit.score,
+                },
+            );
+        }
+
+        if !self.ensure_fat_ready() {
+            return;
+        }
+        let device_hint = self.current_volume_device_index;
+        let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+        let volume = Self::volume_label_text(fat).unwrap_or(String::from("USB"));
+        for hit in crate::search_index::search(query_lower, SEARCH_RESULTS_MAX) {
+            let dir_path = alloc::format!("{}/{}", volume, hit.dir_path.trim_start_matches('/'));
+            let full_path = alloc::format!("{}{}", dir_path, hit.name);
+            let candidate = if hit.is_dir {
+                let folder_path = alloc::format!("{}/", full_path);
+                SearchCandidate {
+                    command: Self::recent_folder_command(
+                        device_hint,
+                        hit.cluster,
+                        folder_path.as_str(),
+                        hit.name.as_str(),
+                    ),
+                    subtitle: alloc::format!("Carpeta: {}", folder_path),
+                    label: hit.name,
+                    score: hit.score,
+                }
+            } else {
+                let shortcut = if hit.name.to_ascii_lowercase().ends_with(".lnk") {
+                    Self::read_shortcut_file_command(fat, hit.cluster, hit.size)
+                } else {
+                    None
+                };
+                let command = shortcut.unwrap_or_else(|| {
+                    Self::recent_file_command(
+                        Self::search_file_kind(hit.name.as_str()),
+                        device_hint,
+                        hit.dir_cluster,
+                        hit.cluster,
+                        hit.size,
+                        dir_path.as_str(),
+                        hit.name.as_str(),
+                    )
+                });
+                SearchCandidate {
+                    command,
+                    subtitle: if hit.in_contents {
+                        alloc::format!("Contiene el texto: {}", full_path)
+                    } else {
+                        alloc::format!("Archivo: {}", full_path)
+                    },
+                    label: hit.name,
+                    score: hit.score,
+                }
+            };
+            Self::push_search_candidate_unique(out, candidate);
+        }
+    }
+
+    fn sorted_search_results(mut candidates: Vec<SearchCandidate>, limit: usize) -> Vec<SearchResultEntry> {
+        candidates.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.label.to_ascii_lowercase().cmp(&b.label.to_ascii_lowercase()))
+        });
+        candidates.truncate(limit);
+        candidates
+            .into_iter()
+            .map(|entry| SearchResultEntry {
+                label: entry.label,
+                subtitle: entry.subtitle,
+                command: entry.command,
+            })
+            .collect()
+    }
+
+    /// Best apps, settings and indexed files for the quick search overlay.
+    fn quick_search_results(&mut self, query: &str) -> Vec<SearchResultEntry> {
+        let trimmed = query.trim();
+        if trimmed.is_empty() {
+            return Vec::new();
+        }
+        let query_lower = Self::ascii_lower(trimmed);
+        let mut candidates: Vec<SearchCandidate> = Vec::new();
+        self.collect_search_app_candidates(query_lower.as_str(), &mut candidates);
+        self.collect_search_index_candidates(query_lower.as_str(), &mut candidates);
+        Self::sorted_search_results(candidates, crate::gui::search_overlay::RESULTS_MAX)
+    }
+
     fn collect_search_fs_candidates_recursive(
         fat: &mut crate::fat32::Fat32,
         dir_cluster: u32,
@@ -3408,17 +4182,8 @@ impl Compositor {
             };
 
             if command.is_none() {
-                let kind = if Self::is_png_file_name(item.label.as_str()) {
-                    "img"
-                } else if Self::is_audio_file_name(item.label.as_str()) {
-            "aud"
-        } else if Self::is_video_file_name(item.label.as_str()) {
-            "vid"
-        } else {
-                    "txt"
-                };
                 command = Some(Self::recent_file_command(
-                    kind,
+                    Self::search_file_kind(item.label.as_str()),
                     device_hint,
                     dir_cluster,
                     item.cluster,
@@ -3468,9 +4233,12 @@ impl Compositor {
 
         self.collect_search_app_candidates(query_lower.as_str(), &mut candidates);
 
-        if self.ensure_fat_ready() {
+        // The index answers at once; until its first walk ends, scan live.
+        if crate::search_index::is_ready() {
+            self.collect_search_index_candidates(query_lower.as_str(), &mut candidates);
+        } else if self.ensure_fat_ready() {
             let (root_cluster, root_path, device_hint) = {
-                let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 let volume = Self::volume_label_text(fat).unwrap_or(String::from("USB"));
                 (
                     fat.root_cluster,
@@ -3479,7 +4247,7 @@ impl Compositor {
                 )
             };
             let mut scanned = 0usize;
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             Self::collect_search_fs_candidates_recursive(
                 fat,
                 root_cluster,
@@ -3492,23 +4260,7 @@ impl Compositor {
             );
         }
 
-        candidates.sort_by(|a, b| {
-            b.score
-                .cmp(&a.score)
-                .then_with(|| a.label.to_ascii_lowercase().cmp(&b.label.to_ascii_lowercase()))
-        });
-        if candidates.len() > SEARCH_RESULTS_MAX {
-            candidates.truncate(SEARCH_RESULTS_MAX);
-        }
-
-        let results: Vec<SearchResultEntry> = candidates
-            .into_iter()
-            .map(|entry| SearchResultEntry {
-                label: entry.label,
-                subtitle: entry.subtitle,
-                command: entry.command,
-            })
-            .collect();
+        let results = Self::sorted_search_results(candidates, SEARCH_RESULTS_MAX);
 
         let status = if results.is_empty() {
             alloc::format!("Sin resultados para '{}'.", trimmed)
@@ -3889,8 +4641,8 @@ impl Compositor {
         alloc::format!("VOL{}", dev.index)
     }
 
-    fn is_double_click_delta(delta: u64) -> bool {
-        delta >= DOUBLE_CLICK_MIN_TICKS && delta <= DOUBLE_CLICK_TICKS
+    fn is_double_click_delta(delta_us: u64) -> bool {
+        crate::gui::interaction::is_double_click(delta_us)
     }
 
     fn trim_wrapping_quotes(text: &str) -> &str {
@@ -4097,6 +4849,20 @@ impl Compositor {
                         Err(_) => false,
                     };
                     if has_file {
+                        // A link, e.g. in a PATH-style bin folder, runs what it points to.
+                        if crate::fsmeta::readlink(fat, dir, leaf.as_str()).is_some() {
+                            let target = crate::fsmeta::resolve(fat, dir, leaf.as_str(), true)?;
+                            if target.entry.is_some_and(|e| e.file_type == FileType::File) {
+                                let path = if target.path.starts_with('/') {
+                                    target.path
+                                } else {
+                                    let parent = candidate.rfind('/').map_or("", |i| &candidate[..=i]);
+                                    alloc::format!("{}{}", parent, target.path)
+                                };
+                                return Ok((target.dir, target.name, path));
+                            }
+                            return Err("enlace roto");
+                        }
                         return Ok((dir, leaf, candidate.clone()));
                     }
                     if fallback.is_none() {
@@ -5742,7 +6508,12 @@ impl Compositor {
                 }
             }
 
-            cluster = next_cluster.ok_or("directorio no encontrado en ruta")?;
+            cluster = match next_cluster {
+                Some(next) => next,
+                // A link to a folder.
+                None => crate::fsmeta::resolve_dir(fat, cluster, dir_name)
+                    .map_err(|_| "directorio no encontrado en ruta")?,
+            };
         }
 
         Ok((cluster, String::from(leaf)))
@@ -6725,214 +7496,25 @@ impl Compositor {
         Self::sha256_hex_from_bytes(&digest)
     }
 
-    fn is_ascii_hex_lower(raw: &str) -> bool {
-        !raw.is_empty()
-            && raw
-                .bytes()
-                .all(|b| (b'0'..=b'9').contains(&b) || (b'a'..=b'f').contains(&b))
-    }
-
     fn verify_install_package_signature(
         package_name: &str,
         package_raw: &[u8],
         signature_text: &str,
         precomputed_sha: Option<String>,
     ) -> Result<(), String> {
-        let mut saw_header = false;
-        let mut algo: Option<String> = None;
-        let mut sig_package: Option<String> = None;
-        let mut sig_size: Option<usize> = None;
-        let mut sig_sha256: Option<String> = None;
-        let mut sig_field: Option<String> = None;
-
-        for line in signature_text.lines() {
-            let trimmed = line.trim();
-            if trimmed.is_empty() {
-                continue;
-            }
-
-            if !saw_header {
-                if Self::ascii_lower(trimmed) != "redux-sig-v1" {
-                    return Err(String::from("firma invalida (.sig header)."));
-                }
-                saw_header = true;
-                continue;
-            }
-
-            let Some(eq_idx) = trimmed.find('=') else {
-                continue;
-            };
-            let key = Self::ascii_lower(trimmed[..eq_idx].trim());
-            let value = trimmed[eq_idx + 1..].trim();
-
-            match key.as_str() {
-                "algo" => algo = Some(String::from(value)),
-                "package" => sig_package = Some(String::from(value)),
-                "size" => {
-                    if !value.is_empty() {
-                        match value.parse::<usize>() {
-                            Ok(v) => sig_size = Some(v),
-                            Err(_) => {
-                                return Err(String::from("firma invalida (.sig SIZE)."));
-                            }
-                        }
-                    }
-                }
-                "sha256" => sig_sha256 = Some(String::from(value)),
-                "sig" => sig_field = Some(String::from(value)),
-                _ => {}
-            }
-        }
-
-        if !saw_header {
-            return Err(String::from("firma invalida (.sig vacia)."));
-        }
-
-        let algo_lower = Self::ascii_lower(algo.unwrap_or_default().trim());
-        if algo_lower != "sha256" {
-            return Err(alloc::format!("firma invalida (ALGO={} no soportado).", algo_lower));
-        }
-
-        if let Some(pkg) = sig_package {
-            let pkg_trim = pkg.trim();
-            if !pkg_trim.is_empty() && Self::ascii_lower(pkg_trim) != Self::ascii_lower(package_name) {
-                return Err(alloc::format!(
-                    "firma invalida (PACKAGE={} != {}).",
-                    pkg_trim,
-                    package_name
-                ));
-            }
-        }
-
-        if let Some(size) = sig_size {
-            if size != package_raw.len() {
-                return Err(alloc::format!(
-                    "firma invalida (SIZE={} != {}).",
-                    size,
-                    package_raw.len()
-                ));
-            }
-        }
-
-        let expected_sha = Self::ascii_lower(sig_sha256.unwrap_or_default().trim());
-        if expected_sha.len() != 64 || !Self::is_ascii_hex_lower(expected_sha.as_str()) {
-            return Err(String::from("firma invalida (SHA256 no valido)."));
-        }
-
-        let computed_sha = precomputed_sha.unwrap_or_else(|| Self::sha256_hex(package_raw));
-        if computed_sha != expected_sha {
-            return Err(alloc::format!(
-                "firma invalida (SHA256 mismatch esperado={} real={}).",
-                expected_sha,
-                computed_sha
-            ));
-        }
-
-        if let Some(sig) = sig_field {
-            let sig_text = Self::ascii_lower(sig.trim());
-            if !sig_text.is_empty() && sig_text != expected_sha {
-                return Err(String::from("firma invalida (SIG mismatch)."));
-            }
-        }
-
-        Ok(())
+        // Hash here so large packages still go through the AP-accelerated path.
+        let sha = precomputed_sha.unwrap_or_else(|| Self::sha256_hex(package_raw));
+        crate::pkg::verify_signature(package_name, package_raw, signature_text, Some(sha))
     }
 
     fn parse_zip_central_directory(
         raw: &[u8],
     ) -> Option<(Vec<(usize, usize, usize, u16)>, usize)> {
-        if raw.len() < 22 {
-            return None;
-        }
-
-        let mut eocd_pos = None;
-        let mut pos = raw.len().saturating_sub(22);
-        loop {
-            if pos + 4 <= raw.len()
-                && raw[pos] == b'P'
-                && raw[pos + 1] == b'K'
-                && raw[pos + 2] == 0x05
-                && raw[pos + 3] == 0x06
-            {
-                eocd_pos = Some(pos);
-                break;
-            }
-            if pos == 0 {
-                break;
-            }
-            pos -= 1;
-        }
-
-        let eocd = eocd_pos?;
-        let mut cursor = eocd + 4;
-        let _disk_number = Self::read_u16_le(raw, &mut cursor)?;
-        let _cd_start_disk = Self::read_u16_le(raw, &mut cursor)?;
-        let _entries_on_disk = Self::read_u16_le(raw, &mut cursor)? as usize;
-        let total_entries = Self::read_u16_le(raw, &mut cursor)? as usize;
-        let central_size = Self::read_u32_le(raw, &mut cursor)? as usize;
-        let central_offset = Self::read_u32_le(raw, &mut cursor)? as usize;
-        let comment_len = Self::read_u16_le(raw, &mut cursor)? as usize;
-
-        if eocd + 22 + comment_len > raw.len() {
-            return None;
-        }
-        if central_offset > raw.len() || central_offset + central_size > raw.len() {
-            return None;
-        }
-
-        let mut entries: Vec<(usize, usize, usize, u16)> = Vec::new();
-        let mut cd_cursor = central_offset;
-        for _ in 0..total_entries {
-            if cd_cursor + 4 > raw.len() {
-                return None;
-            }
-            let sig = u32::from_le_bytes([
-                raw[cd_cursor],
-                raw[cd_cursor + 1],
-                raw[cd_cursor + 2],
-                raw[cd_cursor + 3],
-            ]);
-            if sig != 0x0201_4B50 {
-                return None;
-            }
-            cd_cursor += 4;
-
-            let _version_made = Self::read_u16_le(raw, &mut cd_cursor)?;
-            let _version_needed = Self::read_u16_le(raw, &mut cd_cursor)?;
-            let _flags = Self::read_u16_le(raw, &mut cd_cursor)?;
-            let method = Self::read_u16_le(raw, &mut cd_cursor)?;
-            let _mod_time = Self::read_u16_le(raw, &mut cd_cursor)?;
-            let _mod_date = Self::read_u16_le(raw, &mut cd_cursor)?;
-            let _crc32 = Self::read_u32_le(raw, &mut cd_cursor)?;
-            let comp_u32 = Self::read_u32_le(raw, &mut cd_cursor)?;
-            let uncomp_u32 = Self::read_u32_le(raw, &mut cd_cursor)?;
-            let name_len = Self::read_u16_le(raw, &mut cd_cursor)? as usize;
-            let extra_len = Self::read_u16_le(raw, &mut cd_cursor)? as usize;
-            let file_comment_len = Self::read_u16_le(raw, &mut cd_cursor)? as usize;
-            let _disk_start = Self::read_u16_le(raw, &mut cd_cursor)?;
-            let _int_attr = Self::read_u16_le(raw, &mut cd_cursor)?;
-            let _ext_attr = Self::read_u32_le(raw, &mut cd_cursor)?;
-            let local_offset_u32 = Self::read_u32_le(raw, &mut cd_cursor)?;
-
-            // ZIP64 is not supported in this runtime parser.
-            if comp_u32 == u32::MAX || uncomp_u32 == u32::MAX || local_offset_u32 == u32::MAX {
-                return None;
-            }
-
-            let skip = name_len + extra_len + file_comment_len;
-            if cd_cursor + skip > raw.len() {
-                return None;
-            }
-            cd_cursor += skip;
-
-            entries.push((
-                local_offset_u32 as usize,
-                comp_u32 as usize,
-                uncomp_u32 as usize,
-                method,
-            ));
-        }
-
+        let (entries, central_offset) = crate::archive::zip::read_central_directory(raw).ok()?;
+        let entries = entries
+            .iter()
+            .map(|e| (e.offset, e.stored_size, e.size, e.method))
+            .collect();
         Some((entries, central_offset))
     }
 
@@ -6981,25 +7563,7 @@ impl Compositor {
     }
 
     fn parse_tar_octal(field: &[u8]) -> Option<usize> {
-        let mut value: usize = 0;
-        let mut saw_digit = false;
-        for &b in field {
-            if b == 0 || b == b' ' {
-                if saw_digit {
-                    break;
-                }
-                continue;
-            }
-            if !(b'0'..=b'7').contains(&b) {
-                if saw_digit {
-                    break;
-                }
-                continue;
-            }
-            saw_digit = true;
-            value = value.saturating_mul(8).saturating_add((b - b'0') as usize);
-        }
-        Some(value)
+        crate::archive::tar::parse_octal(field).map(|v| v as usize)
     }
 
     fn extract_gzip_payload(raw: &[u8]) -> Result<Vec<u8>, &'static str> {
@@ -8337,6 +8901,11 @@ impl Compositor {
         lower.ends_with(".png")
     }
 
+    pub fn is_pdf_file_name(name: &str) -> bool {
+        let lower = Self::ascii_lower(name.trim());
+        lower.ends_with(".pdf")
+    }
+
     pub fn png_paeth_predictor(a: u8, b: u8, c: u8) -> u8 {
         let a_i = a as i32;
         let b_i = b as i32;
@@ -8640,6 +9209,10 @@ impl Compositor {
             let max_y = self.height.saturating_sub(1) as i32;
             self.mouse_pos.x = self.mouse_pos.x.saturating_add(dx).clamp(0, max_x);
             self.mouse_pos.y = self.mouse_pos.y.saturating_add(dy).clamp(0, max_y);
+            if let Some((abs_x, abs_y)) = crate::input::take_absolute_pointer() {
+                self.mouse_pos.x = abs_x.clamp(0, max_x);
+                self.mouse_pos.y = abs_y.clamp(0, max_y);
+            }
             moved = true;
             seen += 1;
         }
@@ -9188,11 +9761,108 @@ impl Compositor {
         out
     }
 
+    /// Desktop jobs for the Task Manager list, one row each while not idle.
+    /// Cells follow the process columns: PID, name, ring, state, threads,
+    /// CPU, memory (heap reserved for the job) and network.
+    fn task_manager_job_rows(&self) -> Vec<(TaskManagerTarget, Vec<String>)> {
+        let row = |name: &str, state: String, heap_target: usize| -> Vec<String> {
+            let mem = if heap_target > 0 {
+                crate::perf::format_bytes(heap_target as u64)
+            } else {
+                String::from("-")
+            };
+            alloc::vec![
+                String::from("-"),
+                String::from(name),
+                String::from("job"),
+                state,
+                String::from("-"),
+                String::from("-"),
+                mem,
+                String::from("-"),
+            ]
+        };
+        let progress = |progress_ptr: *const CopyProgressShared| -> String {
+            let progress = unsafe { &*progress_ptr };
+            let (done_units, _, total_units, _) = progress.snapshot();
+            alloc::format!("{}%", done_units.saturating_mul(100) / total_units.max(1))
+        };
+
+        let mut out = Vec::new();
+        let install_state = if let Some(worker) = self.install_task_worker.as_ref() {
+            Some(progress(worker.progress_ptr))
+        } else if self.install_task_waiting_heap {
+            Some(String::from("esperando heap"))
+        } else if self.install_task_active.is_some() {
+            Some(String::from("preparando"))
+        } else if !self.install_task_queue.is_empty() {
+            Some(alloc::format!("{} en cola", self.install_task_queue.len()))
+        } else {
+            None
+        };
+        if let Some(state) = install_state {
+            out.push((
+                TaskManagerTarget::Install,
+                row("Install", state, self.install_task_heap_target_bytes),
+            ));
+        }
+
+        let fs_state = if let Some(worker) = self.terminal_fs_task_worker.as_ref() {
+            Some(progress(worker.progress_ptr))
+        } else if self.terminal_fs_task_waiting_heap {
+            Some(String::from("esperando heap"))
+        } else if self.terminal_fs_task_active.is_some() {
+            Some(String::from("preparando"))
+        } else if !self.terminal_fs_task_queue.is_empty() {
+            Some(alloc::format!("{} en cola", self.terminal_fs_task_queue.len()))
+        } else {
+            None
+        };
+        if let Some(state) = fs_state {
+            out.push((
+                TaskManagerTarget::FsTask,
+                row("FS task", state, self.terminal_fs_task_heap_target_bytes),
+            ));
+        }
+
+        let paste_state = if let Some(worker) = self.clipboard_paste_worker.as_ref() {
+            Some(progress(worker.progress_ptr))
+        } else if self.clipboard_paste_waiting_heap {
+            Some(String::from("esperando heap"))
+        } else if self.clipboard_paste_job.is_some() || self.clipboard_paste_job_busy {
+            Some(String::from("en cola"))
+        } else {
+            None
+        };
+        if let Some(state) = paste_state {
+            out.push((
+                TaskManagerTarget::Paste,
+                row("Clipboard paste", state, self.clipboard_paste_heap_target_bytes),
+            ));
+        }
+
+        if self.linux_runloop_busy || self.linux_runloop_worker_active() {
+            out.push((
+                TaskManagerTarget::LinuxRunloop,
+                row("Linux runloop", String::from("busy"), 0),
+            ));
+        }
+        out
+    }
+
+    /// Sampling happens at most every `sysmon::SAMPLE_INTERVAL_US` and only
+    /// reads counters, so an open Task Manager never stalls the frame.
     fn service_task_manager_windows(&mut self) {
         if !self.windows.iter().any(|w| w.is_task_manager()) {
             return;
         }
-        let lines = self.task_manager_lines();
+        let sampled = crate::sysmon::sample_if_due();
+        let (processes, history) = if sampled {
+            (crate::sysmon::process_rows(), crate::sysmon::history())
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        let jobs = self.task_manager_job_rows();
         let status = self.task_manager_status_line();
 
         for win in self.windows.iter_mut() {
@@ -9200,20 +9870,22 @@ impl Compositor {
                 continue;
             }
             let mut changed = false;
-            if win.task_manager_lines != lines {
-                win.task_manager_lines = lines.clone();
+            if sampled {
+                win.task_manager_processes = processes.clone();
+                win.task_manager_history = history.clone();
                 changed = true;
             }
+            if win.task_manager_jobs != jobs {
+                win.task_manager_jobs = jobs.clone();
+                changed = true;
+            }
+            if changed {
+                win.rebuild_task_manager_list();
+            }
             if win.task_manager_status != status {
                 win.task_manager_status = status.clone();
                 changed = true;
             }
-            if let Some(sel) = win.task_manager_selected {
-                if sel >= win.task_manager_lines.len() {
-                    win.task_manager_selected = None;
-                    changed = true;
-                }
-            }
             if changed {
                 win.render();
             }
@@ -9399,7 +10071,7 @@ impl Compositor {
     }
 
     fn ensure_global_fat32_write_target() -> Result<(), String> {
-        let fat = unsafe { &crate::fat32::GLOBAL_FAT };
+        let fat = unsafe { crate::fat32::GLOBAL_FAT.get() };
         Self::ensure_fat32_write_target(fat)
     }
 
@@ -9426,6 +10098,7 @@ impl Compositor {
             win_id,
             priority,
             kind,
+            cred: crate::perm::acting(),
         };
 
         let mut insert_idx = self.terminal_fs_task_queue.len();
@@ -9533,6 +10206,7 @@ impl Compositor {
     }
 
     fn execute_terminal_fs_task(&mut self, task: &TerminalFsTask) -> Vec<String> {
+        let _acting = crate::perm::act_as(task.cred);
         let mut out = Vec::new();
         match task.kind {
             TerminalFsTaskKind::CopyMoveLocal {
@@ -9558,7 +10232,7 @@ impl Compositor {
                     self.current_volume_device_index = volume_index_hint;
                 }
 
-                let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 if fat.bytes_per_sector == 0 {
                     self.current_volume_device_index = prev_volume;
                     out.push(alloc::format!(
@@ -9735,6 +10409,7 @@ impl Compositor {
         progress: &CopyProgressShared,
         worker_job_id: u64,
     ) -> (Vec<String>, bool) {
+        let _acting = crate::perm::act_as(task.cred);
         let mut out = Vec::new();
         let mut ok = true;
 
@@ -9757,7 +10432,7 @@ impl Compositor {
                     return (out, false);
                 }
 
-                let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 if fat.bytes_per_sector == 0 {
                     out.push(alloc::format!(
                         "Task #{} CP/MV error: volumen no montado.",
@@ -9947,7 +10622,7 @@ impl Compositor {
                 {
                     return Err(String::from("origen y destino iguales"));
                 }
-                let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 if fat.bytes_per_sector == 0 {
                     return Err(String::from("volumen no montado"));
                 }
@@ -10769,6 +11444,296 @@ impl Compositor {
         }
     }
 
+    /// Open the login page of a captive portal found by `net::portal`.
+    fn service_captive_portal(&mut self) {
+        let Some(url) = crate::net::portal::take_open_request() else {
+            return;
+        };
+        let browser_id = self.create_browser_window("Portal cautivo", 180, 60, 800, 500);
+        self.browser_navigate_to(browser_id, url.as_str());
+    }
+
+    /// Follow USB devices plugged in or pulled out: offer to mount a new
+    /// stick and unmount the volume whose stick is gone.
+    fn service_usb_hotplug(&mut self) {
+        let changes = crate::usb::poll();
+        if !changes.devices_changed {
+            return;
+        }
+        let mounted = unsafe { crate::fat32::GLOBAL_FAT.get() }
+            .uefi_block_handle
+            .map(|handle| handle.as_ptr() as usize);
+        if mounted.is_some_and(|handle| changes.removed_volumes.contains(&handle)) {
+            let disk_index = self.current_volume_device_index.unwrap_or(0);
+            let _ = self.unmount_disk_volume(disk_index);
+            // Nothing was ejected by hand; let the next volume mount normally.
+            self.clear_manual_unmount_lock();
+            self.desktop_surface_status = String::from("USB: unidad retirada, volumen desmontado.");
+            crate::gui::notifications::post(
+                "usb",
+                "Unidad USB retirada",
+                "El volumen montado se ha desmontado.",
+                crate::gui::notifications::Urgency::Warning,
+            );
+        }
+        self.refresh_desktop_disk_icons(true);
+        if let Some(volume) = changes.new_volumes.into_iter().next() {
+            if self.modal_dialog.is_none() {
+                let text = alloc::format!("Se ha conectado {}. Quieres montarla?", volume.label);
+                self.open_dialog(
+                    Dialog::confirm("Unidad USB conectada", text.as_str(), "Montar"),
+                    DialogPurpose::MountUsb(volume.handle),
+                );
+            }
+        }
+        self.needs_repaint = true;
+    }
+
+    /// Ask before enabling PCIe devices that came in over Thunderbolt/USB4.
+    fn service_thunderbolt(&mut self) {
+        crate::thunderbolt::poll();
+        if self.modal_dialog.is_some() {
+            return;
+        }
+        let Some(attachment) = crate::thunderbolt::next_prompt() else {
+            return;
+        };
+        let text = alloc::format!(
+            "Se ha conectado {} ({} funciones PCIe) por Thunderbolt/USB4. Un dispositivo PCIe puede leer toda la memoria; autorizalo solo si confias en el. Autorizar?",
+            attachment.description(),
+            attachment.functions.len()
+        );
+        self.open_dialog(
+            Dialog::confirm("Dispositivo Thunderbolt", text.as_str(), "Autorizar"),
+            DialogPurpose::AuthorizeThunderbolt(attachment.port),
+        );
+        self.needs_repaint = true;
+    }
+
+    /// Collect finished `fetch` requests: save the payload, or move on to
+    /// the next candidate URL after a network failure or a 404.
+    fn service_pending_fetches(&mut self) {
+        let mut i = 0usize;
+        while i < self.pending_fetches.len() {
+            let crate::net::worker::Poll::Ready(raw) = crate::net::worker::poll(self.pending_fetches[i].request_id)
+            else {
+                i += 1;
+                continue;
+            };
+            let mut fetch = self.pending_fetches.remove(i);
+            let mut out = Vec::new();
+            let win_id = fetch.win_id;
+            if Self::fetch_advance(&mut fetch, raw, &mut out) {
+                self.pending_fetches.insert(i, fetch);
+                i += 1;
+            }
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+        }
+    }
+
+    /// Show pages and WebKit bridge replies whose tasks finished.
+    fn service_browser_loads(&mut self) {
+        let mut i = 0usize;
+        while i < self.browser_loads.len() {
+            let Some(loaded) = self.browser_loads[i].job.try_take() else {
+                i += 1;
+                continue;
+            };
+            let load = self.browser_loads.remove(i);
+            if let Some(endpoint) = loaded.endpoint {
+                self.web_proxy_endpoint_base = endpoint;
+            }
+            self.browser_show_render(load.win_id, load.url.as_str(), Some(loaded.render));
+        }
+        let mut i = 0usize;
+        while i < self.browser_bridge_inputs.len() {
+            let Some(sent) = self.browser_bridge_inputs[i].job.try_take() else {
+                i += 1;
+                continue;
+            };
+            let input = self.browser_bridge_inputs.remove(i);
+            self.browser_cef_show_input(input.win_id, sent);
+        }
+    }
+
+    /// Run a terminal command's network part on an executor task;
+    /// `service_terminal_net_jobs` prints its lines when it is done.
+    fn terminal_spawn_net_job(
+        &mut self,
+        win_id: usize,
+        out: &mut Vec<String>,
+        job: impl core::future::Future<Output = (Vec<String>, Option<String>)> + 'static,
+    ) {
+        match crate::executor::spawn_joinable("terminal-net", job) {
+            Some(job) => self.terminal_net_jobs.push(TerminalNetJob { win_id, job }),
+            None => out.push(String::from("Sin tareas libres; reintenta en un momento.")),
+        }
+    }
+
+    /// Print the lines of terminal commands whose requests finished.
+    fn service_terminal_net_jobs(&mut self) {
+        let mut i = 0usize;
+        while i < self.terminal_net_jobs.len() {
+            let Some((lines, endpoint)) = self.terminal_net_jobs[i].job.try_take() else {
+                i += 1;
+                continue;
+            };
+            let job = self.terminal_net_jobs.remove(i);
+            if let Some(endpoint) = endpoint {
+                self.web_proxy_endpoint_base = endpoint;
+            }
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == job.win_id) {
+                for line in lines.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+        }
+    }
+
+    /// Collect `json_get` answers and, once none is left in flight, run
+    /// again the IDE scripts that were waiting for them.
+    fn service_ide_json_fetches(&mut self) {
+        let mut i = 0usize;
+        while i < self.ide_json_fetches.len() {
+            let Some(result) = self.ide_json_fetches[i].job.try_take() else {
+                i += 1;
+                continue;
+            };
+            let fetch = self.ide_json_fetches.remove(i);
+            self.ide_json_results.push((fetch.url, result));
+        }
+        if !self.ide_json_fetches.is_empty() {
+            return;
+        }
+        for run in core::mem::take(&mut self.ide_runs_waiting) {
+            let status = match self.ide_rust_run_ui_bridge_block(
+                run.scope,
+                run.win_id,
+                run.source.as_str(),
+                run.label.as_str(),
+                run.reset_preview,
+            ) {
+                Ok(Some(status)) => status,
+                Ok(None) => continue,
+                Err(err) => alloc::format!("Rust API error: {}", err),
+            };
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == run.win_id) {
+                match run.scope {
+                    IdeRuntimeScope::Preview => win.ide_set_status(status.as_str()),
+                    IdeRuntimeScope::AppRunner => {
+                        win.app_runner_status = status;
+                        win.render();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handle the response to the current candidate; true when the next
+    /// candidate was submitted and the fetch is still pending.
+    fn fetch_advance(fetch: &mut PendingFetch, raw: Option<Vec<u8>>, out: &mut Vec<String>) -> bool {
+        if let Some(raw) = raw {
+            let (status_code, payload) = Self::extract_http_status_and_body_bytes(raw.as_slice());
+            match status_code {
+                Some(code) if (300..400).contains(&code) => {
+                    out.push(alloc::format!(
+                        "Fetch error: HTTP {} redirect not followed (too many hops or no Location).",
+                        code
+                    ));
+                    return false;
+                }
+                Some(404) if fetch.index + 1 < fetch.candidates.len() => {}
+                Some(code) if code >= 400 => {
+                    out.push(alloc::format!("Fetch error: HTTP {}", code));
+                    return false;
+                }
+                _ => {
+                    Self::fetch_save(fetch, payload, out);
+                    return false;
+                }
+            }
+        }
+
+        fetch.index += 1;
+        if fetch.index >= fetch.candidates.len() {
+            out.push(String::from("Fetch error: network request failed."));
+            return false;
+        }
+        out.push(alloc::format!("Fetch retry: {}", fetch.candidates[fetch.index]));
+        if !fetch.submit() {
+            out.push(String::from("Fetch error: network request failed."));
+            return false;
+        }
+        true
+    }
+
+    fn fetch_save(fetch: &PendingFetch, mut payload: Vec<u8>, out: &mut Vec<String>) {
+        if payload.is_empty() {
+            out.push(String::from("Fetch error: empty response body."));
+            return;
+        }
+        if payload.len() > FETCH_MAX_FILE_BYTES {
+            payload.truncate(FETCH_MAX_FILE_BYTES);
+            out.push(alloc::format!("Fetch warning: truncated to {} bytes.", FETCH_MAX_FILE_BYTES));
+        }
+        if fetch.index > 0 {
+            out.push(alloc::format!("Fetch final URL: {}", fetch.candidates[fetch.index]));
+        }
+
+        let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+        let file_name = fetch.file_name.as_str();
+        match fat.write_text_file_in_dir(fetch.dir_cluster, file_name, payload.as_slice()) {
+            Ok(()) => {
+                out.push(alloc::format!("Saved {} bytes to {}", payload.len(), file_name));
+                crate::gui::notifications::post(
+                    "fetch",
+                    "Descarga completa",
+                    alloc::format!("{} ({} bytes)", file_name, payload.len()).as_str(),
+                    crate::gui::notifications::Urgency::Success,
+                );
+                if file_name.ends_with(".RB") {
+                    out.push(alloc::format!("Run with: ruby {}", file_name));
+                } else if fetch.repo_mode {
+                    out.push(String::from("Tip: fetch a .rb file from repo and run `ruby <file>.`"));
+                }
+            }
+            Err(err) => {
+                out.push(alloc::format!("Fetch error: {}", err));
+            }
+        }
+    }
+
+    /// Output of programs bound to terminal windows, and their exit status.
+    /// A non-zero status is also posted as a notification.
+    fn service_process_env(&mut self) {
+        for event in crate::procenv::take_events() {
+            match event {
+                crate::procenv::Event::Output { win_id, line } => {
+                    self.append_terminal_lines(win_id, core::slice::from_ref(&line));
+                }
+                crate::procenv::Event::Exited { name, code, win_id, .. } => {
+                    if let Some(win_id) = win_id {
+                        self.append_terminal_lines(win_id, &[alloc::format!("[{} termino: codigo {}]", name, code)]);
+                    }
+                    if code != 0 {
+                        crate::gui::notifications::post(
+                            name.as_str(),
+                            "Programa terminado con error",
+                            alloc::format!("{} salio con codigo {}", name, code).as_str(),
+                            crate::gui::notifications::Urgency::Error,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
     fn service_terminal_streams(&mut self) {
         if self.terminal_stream_queues.is_empty() {
             return;
@@ -11094,7 +12059,7 @@ impl Compositor {
         while executed < budget && step.active {
             match step.stage {
                 LinuxProcStage::ResolveTarget => {
-                    let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                     if fat.bytes_per_sector == 0 && !fat.init() {
                         step.active = false;
                         step.stage = LinuxProcStage::Failed;
@@ -11198,7 +12163,7 @@ impl Compositor {
                     ));
                 }
                 LinuxProcStage::ReadMainElf => {
-                    let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                     let Some(entry) = step.target_entry else {
                         step.active = false;
                         step.stage = LinuxProcStage::Failed;
@@ -11461,7 +12426,7 @@ impl Compositor {
                     step.last_note = String::from("inspect main listo");
                 }
                 LinuxProcStage::InspectDynamic => {
-                    let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                     let report = match crate::linux_compat::inspect_elf64(step.raw.as_slice()) {
                         Ok(v) => v,
                         Err(err) => {
@@ -11655,7 +12620,7 @@ impl Compositor {
                     }
                 }
                 LinuxProcStage::CollectRuntime => {
-                    let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                     let Some(lookup) = step.runtime_lookup else {
                         step.stage = LinuxProcStage::Summarize;
                         step.last_note = String::from("lookup runtime no disponible");
@@ -11994,6 +12959,18 @@ impl Compositor {
     }
 
     #[inline]
+    /// Guest working directory for a program launched from a terminal at
+    /// `path` ("<volumen>/DIR/.../"). The guest root is LINUXRT on the
+    /// volume, so anything outside it starts at "/".
+    fn linux_guest_cwd(path: &str) -> String {
+        let rest = path.split_once('/').map(|(_, rest)| rest).unwrap_or("");
+        let lower = Self::ascii_lower(rest);
+        match lower.strip_prefix("linuxrt") {
+            Some(inside) if inside.is_empty() || inside.starts_with('/') => crate::procenv::normalize_path(inside),
+            _ => String::from("/"),
+        }
+    }
+
     fn linux_runloop_worker_active(&self) -> bool {
         self.linux_runloop_worker.is_some()
     }
@@ -12386,7 +13363,7 @@ impl Compositor {
         let mut effective_target_program = target_program.clone();
         if self.ensure_fat_ready() {
             let resolved_current_volume = {
-                let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 let base_cluster = self.terminal_current_cluster(win_id, fat);
                 Self::terminal_program_exists_on_volume(
                     fat,
@@ -12524,10 +13501,22 @@ impl Compositor {
         ));
         crate::syscall::linux_gfx_bridge_open(LINUX_BRIDGE_DEFAULT_WIDTH, LINUX_BRIDGE_DEFAULT_HEIGHT);
         crate::syscall::linux_gfx_bridge_set_direct_present(false);
+        // Launched from a terminal: cwd and stdio follow that window.
+        // Helpers started by other windows get no console.
+        let (stdio, cwd) = match self.windows.iter().find(|w| w.id == win_id) {
+            Some(win) if win.is_terminal() => (
+                crate::procenv::Stdio::terminal(win_id),
+                Self::linux_guest_cwd(win.current_path.as_str()),
+            ),
+            _ => (crate::procenv::Stdio::detached(), String::from("/")),
+        };
+        let proc_name = effective_target_program.rsplit('/').next().unwrap_or("linux");
+        let proc_id = crate::procenv::spawn(proc_name, cwd.as_str(), stdio);
         if let Some(run) = self.linux_runloop_container.as_mut() {
             run.update_progress(0, 0);
             run.argv_items = argv_items;
             run.execfn = effective_target_program;
+            run.proc_id = proc_id;
         }
         self.refresh_linux_runloop_snapshot();
         crate::syscall::linux_gfx_bridge_set_status(
@@ -12586,6 +13575,7 @@ impl Compositor {
                 run.stage = LinuxRunLoopStage::Stopped;
                 run.update_progress(100, run.progress_overall);
                 run.last_note = String::from("detenido por usuario");
+                crate::procenv::kill(run.proc_id);
                 if crate::syscall::linux_shim_active() {
                     let _ = crate::syscall::linux_shim_invoke(231, 0, 0, 0, 0, 0, 0);
                 }
@@ -12905,7 +13895,7 @@ impl Compositor {
                         break;
                     }
 
-                    let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                     let mut interp_raw = match Self::try_alloc_zeroed(interp_entry.size as usize) {
                         Ok(buf) => buf,
                         Err(err) => {
@@ -13027,7 +14017,7 @@ impl Compositor {
                         continue;
                     }
 
-                    let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                     let job = run.dep_load_jobs[run.dep_load_cursor].clone();
 
                     if run.dep_load_active_payload.is_empty() {
@@ -13680,7 +14670,7 @@ impl Compositor {
                                         let chunk_len = (total_blob - cursor)
                                             .min(LINUX_MAIN_ELF_READ_CHUNK_BYTES.max(4096));
                                         let dst = &mut run.runtime_blob_active_payload[cursor..cursor + chunk_len];
-                                        let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                                        let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                                         match fat.read_file_range(
                                             entry.cluster,
                                             total_blob,
@@ -13963,6 +14953,7 @@ impl Compositor {
                         crate::syscall::linux_gfx_bridge_set_status(
                             "Linux runloop: proceso finalizado, retorno seguro al escritorio.",
                         );
+                        crate::procenv::exit(run.proc_id, shim.exit_code);
                         out.push(alloc::format!(
                             "Linux runloop: proceso finalizado (exit_code={} watchdog={} calls={}).",
                             shim.exit_code,
@@ -14263,6 +15254,7 @@ impl Compositor {
                         crate::syscall::linux_gfx_bridge_set_status(
                             "Linux runloop: proceso finalizado, retorno seguro al escritorio.",
                         );
+                        crate::procenv::exit(run.proc_id, slice.exit_code);
                         out.push(alloc::format!(
                             "Linux runloop: proceso finalizado (exit_code={} watchdog={} calls={}).",
                             slice.exit_code,
@@ -14344,6 +15336,10 @@ impl Compositor {
         if executed == 0 && run.active {
             out.push(String::from("Linux runloop: sin avance (reintentar)."));
         }
+        if run.stage == LinuxRunLoopStage::Failed {
+            // Never started, or the session broke: report it like a failed exit.
+            crate::procenv::exit(run.proc_id, 1);
+        }
         self.linux_runloop_container = Some(run);
         self.refresh_linux_runloop_snapshot();
         out
@@ -14527,8 +15523,15 @@ impl Compositor {
             minimized_overflow_open: false,
             minimized_overflow_scroll: 0,
             clock_panel_open: false,
+            notify_panel_open: false,
+            notify_panel_scroll: 0,
+            desktop_background: DEFAULT_DESKTOP_BACKGROUND,
             last_mouse_down: false,
             last_mouse_right_down: false,
+            event_time_us: 0,
+            clicks: ClickTracker::default(),
+            drag_session: None,
+            window_context_menu: None,
             mouse_input_priority_frames: 0,
             mouse_runloop_throttle_skip: 0,
             taskbar_window,
@@ -14541,6 +15544,7 @@ impl Compositor {
             desktop_context_menu: None,
             ide_context_menu: None,
             ide_selection_drag: None,
+            notepad_selection_drag: None,
             ide_text_clipboard: String::new(),
             explorer_clipboard: None,
             pointer_capture: None,
@@ -14596,6 +15600,10 @@ impl Compositor {
             headless_install_units_reported: 0,
             headless_install_items_reported: 0,
             notepad_save_prompt: None,
+            modal_dialog: None,
+            quick_search: None,
+            fs_watch: None,
+            capture_meter: None,
             ide_unsaved_prompt: None,
             ide_post_export_action: None,
             manual_unmount_lock: false,
@@ -14612,6 +15620,14 @@ impl Compositor {
             linux_runloop_snapshot_tick: 0,
             linux_runloop_snapshot_active: false,
             terminal_stream_queues: Vec::new(),
+            pending_fetches: Vec::new(),
+            browser_loads: Vec::new(),
+            browser_bridge_inputs: Vec::new(),
+            terminal_net_jobs: Vec::new(),
+            ide_json_fetches: Vec::new(),
+            ide_json_results: Vec::new(),
+            ide_json_used: Vec::new(),
+            ide_runs_waiting: Vec::new(),
             terminal_stream_auto_tune: true,
             terminal_stream_global_budget: TERMINAL_STREAM_FLUSH_GLOBAL_LINES_PER_FRAME,
             terminal_stream_per_window_budget: TERMINAL_STREAM_FLUSH_PER_WINDOW_LINES,
@@ -14638,21 +15654,96 @@ impl Compositor {
             ide_runtime_state: Vec::new(),
             desktop_scroll: 0,
             needs_repaint: true,
+            animations: Vec::new(),
             pinned_context_menu_index: None,
             is_suspended: false,
             suspend_ignore_mouse_until_release: false,
         };
         comp.refresh_desktop_disk_icons(true);
+        crate::config::watch("desktop.", on_desktop_config_changed);
+        crate::config::watch(crate::i18n::LOCALE_KEY, on_desktop_config_changed);
+        comp.apply_desktop_theme();
         comp
     }
 
+    /// Colors and UI scale from the `desktop.*` config keys.
+    fn apply_desktop_theme(&mut self) {
+        self.desktop_background = crate::config::get_color(DESKTOP_BACKGROUND_KEY, DEFAULT_DESKTOP_BACKGROUND);
+        self.taskbar.background = crate::config::get_color(DESKTOP_TASKBAR_KEY, DEFAULT_TASKBAR_BACKGROUND);
+        self.apply_ui_scale(crate::gui::hidpi::configured_percent());
+        self.needs_repaint = true;
+    }
+
+    fn apply_ui_scale(&mut self, pct: u32) {
+        if self.headless || pct == framebuffer::scale_percent() {
+            return;
+        }
+        match framebuffer::set_scale_percent(pct) {
+            Ok((width, height)) => {
+                self.resize_screen(width, height);
+                crate::klog::log(
+                    crate::klog::Level::Info,
+                    alloc::format!("gui: escala {} -> escritorio {}x{}", crate::gui::hidpi::format_scale(pct), width, height)
+                        .as_str(),
+                );
+            }
+            Err(e) => {
+                crate::klog::log(crate::klog::Level::Warning, alloc::format!("gui: escala: {}", e).as_str());
+                crate::gui::notifications::post(
+                    "desktop",
+                    "Escala de interfaz",
+                    e,
+                    crate::gui::notifications::Urgency::Warning,
+                );
+            }
+        }
+    }
+
+    /// Relayout for a new logical screen size (after a UI scale change).
+    fn resize_screen(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        crate::input::set_screen_dimensions(width as u32, height as u32);
+        self.mouse_pos.x = self.mouse_pos.x.clamp(0, width as i32 - 1);
+        self.mouse_pos.y = self.mouse_pos.y.clamp(0, height as i32 - 1);
+
+        let taskbar_h = self.taskbar.rect.height;
+        self.taskbar.rect = Rect::new(0, height as i32 - taskbar_h as i32, width as u32, taskbar_h);
+        self.taskbar_window = Window::new(9999, "Taskbar", 0, self.taskbar.rect.y, width as u32, taskbar_h);
+
+        for win in self.windows.iter_mut() {
+            if win.state == WindowState::Maximized {
+                // Toggle back out and in to refit the new screen.
+                win.maximize(width, height);
+                win.maximize(width, height);
+                continue;
+            }
+            let max_x = (width as i32 - win.rect.width as i32).max(0);
+            let max_y = (height as i32 - taskbar_h as i32 - WINDOW_TITLE_BAR_H).max(0);
+            let (x, y) = (win.rect.x.clamp(0, max_x), win.rect.y.clamp(0, max_y));
+            if (x, y) != (win.rect.x, win.rect.y) {
+                win.rect.x = x;
+                win.rect.y = y;
+                win.controls = crate::gui::window::WindowControls::new(x, y, win.rect.width);
+            }
+        }
+    }
+
+    fn service_desktop_theme(&mut self) {
+        if DESKTOP_THEME_CHANGED.swap(false, Ordering::AcqRel) {
+            self.apply_desktop_theme();
+        }
+    }
+
     fn attach_new_window(&mut self, mut win: Window) -> usize {
         let id = win.id;
         win.desktop_id = self.active_desktop_id();
         let kind = win.kind;
         let title = win.title.clone();
+        let (rect, opacity) = (win.rect, win.opacity);
         self.windows.push(win);
         self.active_window_id = Some(id);
+        self.start_window_animation(id, crate::gui::effects::Kind::Open, rect, rect, opacity);
         if let Some(app_key) = Self::recent_app_key_for_window_kind(kind) {
             let command = Self::recent_app_command(app_key);
             self.set_window_recent_binding(id, title.as_str(), command.as_str());
@@ -14860,11 +15951,131 @@ impl Compositor {
         let id = self.next_id;
         self.next_id += 1;
         let mut win = Window::new_task_manager(id, title, x, y, width, height);
-        win.task_manager_lines = self.task_manager_lines();
+        crate::sysmon::sample_if_due();
+        win.task_manager_processes = crate::sysmon::process_rows();
+        win.task_manager_history = crate::sysmon::history();
+        win.task_manager_jobs = self.task_manager_job_rows();
+        win.rebuild_task_manager_list();
         win.task_manager_status = self.task_manager_status_line();
         self.attach_new_window(win)
     }
 
+    pub fn create_mail_window(
+        &mut self,
+        title: &str,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut win = Window::new_mail(id, title, x, y, width, height);
+        if crate::net::mail::load_account().is_none() {
+            win.mail_status = String::from("Sin cuenta: pulsa Cuenta para configurar el correo.");
+            win.render();
+        }
+        self.attach_new_window(win)
+    }
+
+    pub fn create_about_pc_window(
+        &mut self,
+        title: &str,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let win = Window::new_about_pc(id, title, x, y, width, height);
+        self.attach_new_window(win)
+    }
+
+    pub fn create_boot_entries_window(
+        &mut self,
+        title: &str,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let win = Window::new_boot_entries(id, title, x, y, width, height);
+        self.attach_new_window(win)
+    }
+
+    pub fn create_pdf_viewer_window(
+        &mut self,
+        title: &str,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let win = Window::new_pdf_viewer(id, title, x, y, width, height);
+        self.attach_new_window(win)
+    }
+
+    pub fn create_calculator_window(
+        &mut self,
+        title: &str,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let win = Window::new_calculator(id, title, x, y, width, height);
+        self.attach_new_window(win)
+    }
+
+    pub fn create_calendar_window(
+        &mut self,
+        title: &str,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let win = Window::new_calendar(id, title, x, y, width, height);
+        self.attach_new_window(win)
+    }
+
+    pub fn create_clock_window(
+        &mut self,
+        title: &str,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let win = Window::new_clock(id, title, x, y, width, height);
+        self.attach_new_window(win)
+    }
+
+    pub fn create_recorder_window(
+        &mut self,
+        title: &str,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let win = Window::new_recorder(id, title, x, y, width, height);
+        self.attach_new_window(win)
+    }
+
     pub fn create_video_player_window(
         &mut self,
         title: &str,
@@ -14928,7 +16139,7 @@ impl Compositor {
                 removable,
                 is_boot,
                 menu_open: existing.map(|e| e.menu_open).unwrap_or(false),
-                last_click_tick: existing.map(|e| e.last_click_tick).unwrap_or(0),
+                last_click_us: existing.map(|e| e.last_click_us).unwrap_or(0),
                 rect,
             });
 
@@ -15110,7 +16321,7 @@ impl Compositor {
     }
 
     fn unmount_disk_volume(&mut self, disk_index: usize) -> String {
-        let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+        let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
         if fat.bytes_per_sector == 0 {
             return String::from("Unmount: no hay volumen montado.");
         }
@@ -15211,14 +16422,14 @@ impl Compositor {
                 if i != idx { icon.menu_open = false; }
             }
 
-            let now = crate::timer::ticks();
+            let now = self.event_time_us;
             let is_double_click = Self::is_double_click_delta(
-                now.saturating_sub(self.desktop_disk_icons[idx].last_click_tick),
+                now.saturating_sub(self.desktop_disk_icons[idx].last_click_us),
             );
-            self.desktop_disk_icons[idx].last_click_tick = now;
+            self.desktop_disk_icons[idx].last_click_us = now;
             
             if is_double_click {
-                self.desktop_disk_icons[idx].last_click_tick = 0;
+                self.desktop_disk_icons[idx].last_click_us = 0;
                 let device_index = self.desktop_disk_icons[idx].device_index;
                 self.open_desktop_disk_in_explorer(device_index);
             } else {
@@ -15283,7 +16494,7 @@ impl Compositor {
                 continue;
             }
             let outcome = {
-                let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 let volume_label = Self::explorer_volume_label_for_mounted(index, fat);
                 match Self::resolve_named_root_dir_cluster(fat, shortcut_name, false) {
                     Ok((cluster, _created)) => {
@@ -15298,7 +16509,7 @@ impl Compositor {
 
             if create_if_missing {
                 let create_outcome = {
-                    let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                     if fat.mounted_fs != crate::fat32::DetectedFsKind::ExFat {
                         None
                     } else {
@@ -15340,7 +16551,7 @@ impl Compositor {
                     continue;
                 }
                 let outcome = {
-                    let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                     let volume_label = Self::explorer_volume_label_for_mounted(index, fat);
                     match Self::resolve_named_root_dir_cluster(fat, shortcut_name, true) {
                         Ok((cluster, _created)) => {
@@ -15416,7 +16627,7 @@ impl Compositor {
             return None;
         }
         let mut items = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             Self::build_explorer_dir_items(fat, desktop_cluster)
         };
         items.retain(|item| {
@@ -16227,7 +17438,7 @@ impl Compositor {
         }
 
         let command_text = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             if let Some(cmd) = Self::read_shortcut_file_command(fat, item.cluster, item.size) {
                 cmd
             } else {
@@ -16295,7 +17506,7 @@ impl Compositor {
             if let Some(index) = source_device_index {
                 let _ = self.ensure_volume_index_mounted(index);
             }
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             let _ = fat.ensure_subdirectory(fat.root_cluster, "TRASH");
             let trash_cluster = fat.resolve_path(fat.root_cluster, "TRASH/").map(|(_, c)| c).unwrap_or(0);
             
@@ -16315,7 +17526,7 @@ impl Compositor {
             let explorer_id = self.create_explorer_window("File Explorer - Inicio", 140, 80, 920, 580);
             if let Some(win) = self.windows.iter_mut().find(|w| w.id == explorer_id) {
                 win.explorer_path = String::from("/");
-                win.explorer_current_cluster = unsafe { crate::fat32::GLOBAL_FAT.root_cluster };
+                win.explorer_current_cluster = unsafe { crate::fat32::GLOBAL_FAT.get().root_cluster };
             }
             self.desktop_surface_status = String::from("Inicio abierto.");
             return;
@@ -16329,6 +17540,8 @@ impl Compositor {
 
             if Self::is_png_file_name(item.label.as_str()) {
                 self.open_png_from_explorer_file(0, item);
+            } else if Self::is_pdf_file_name(item.label.as_str()) {
+                self.open_pdf_from_explorer_file(0, item);
             } else if Self::is_audio_file_name(item.label.as_str()) {
                 self.open_media_player_file(item.cluster, item.label.as_str(), item.size);
             } else if Self::is_video_file_name(item.label.as_str()) {
@@ -16356,14 +17569,14 @@ impl Compositor {
             return false;
         };
 
-        let now = crate::timer::ticks();
+        let now = self.event_time_us;
         let mut is_double_click = false;
         if let Some(prev) = &self.last_explorer_click {
             if prev.win_id == 0
                 && prev.kind == item.kind
                 && prev.cluster == item.cluster
                 && prev.label.eq_ignore_ascii_case(item.label.as_str())
-                && Self::is_double_click_delta(now.saturating_sub(prev.tick))
+                && Self::is_double_click_delta(now.saturating_sub(prev.at_us))
             {
                 is_double_click = true;
             }
@@ -16374,7 +17587,7 @@ impl Compositor {
             kind: item.kind,
             cluster: item.cluster,
             label: item.label.clone(),
-            tick: now,
+            at_us: now,
         });
 
         if is_double_click {
@@ -16462,6 +17675,7 @@ impl Compositor {
         }
 
         self.desktop_create_folder = Some(DesktopCreateFolderState {
+            kind: DesktopPromptKind::CreateFolder,
             dir_cluster,
             dir_path,
             device_index: self
@@ -16484,6 +17698,7 @@ impl Compositor {
             .unwrap_or_else(|_| (String::from("Desktop/"), self.current_volume_device_index));
 
         self.desktop_create_folder = Some(DesktopCreateFolderState {
+            kind: DesktopPromptKind::CreateFolder,
             dir_cluster,
             dir_path,
             device_index,
@@ -16493,15 +17708,79 @@ impl Compositor {
             String::from("Crear carpeta: escribe nombre y presiona Enter.");
     }
 
+    fn begin_explorer_connect_server(&mut self, win_id: usize) {
+        let (dir_cluster, dir_path, device_index) = match self.windows.iter().find(|w| w.id == win_id) {
+            Some(win) => (
+                win.explorer_current_cluster,
+                win.explorer_path.clone(),
+                win.explorer_device_index.or(self.current_volume_device_index),
+            ),
+            None => return,
+        };
+
+        self.desktop_create_folder = Some(DesktopCreateFolderState {
+            kind: DesktopPromptKind::ConnectServer,
+            dir_cluster,
+            dir_path,
+            device_index,
+            input: String::from("ftp://"),
+        });
+        if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+            win.set_explorer_status("Conectar a servidor: escribe ftp[s]://host y presiona Enter.");
+        }
+    }
+
+    fn commit_explorer_connect_server(&mut self, prompt: DesktopCreateFolderState) {
+        let url = prompt.input.trim();
+        if url.is_empty() || url == "ftp://" || url == "ftps://" {
+            self.desktop_surface_status = String::from("Direccion vacia. Escribe ftp[s]://host.");
+            return;
+        }
+        if redux_netparse::ftp::parse_ftp_target(url).is_none() {
+            self.desktop_surface_status = String::from("Direccion invalida (ftp://host o ftps://host).");
+            return;
+        }
+        self.desktop_create_folder = None;
+
+        // Downloads land in the folder the Explorer was showing.
+        let term_id = self
+            .windows
+            .iter()
+            .find(|w| w.is_terminal())
+            .map(|w| w.id)
+            .unwrap_or_else(|| self.create_window("Terminal Shell", 100, 100, 800, 500));
+        if prompt.dir_cluster >= 2 {
+            if let Some(term) = self.windows.iter_mut().find(|w| w.id == term_id) {
+                let mut path_text = prompt.dir_path.clone();
+                if !path_text.ends_with('/') {
+                    path_text.push('/');
+                }
+                term.current_dir_cluster = prompt.dir_cluster;
+                term.current_path = path_text;
+            }
+        }
+        self.enqueue_terminal_command(term_id, alloc::format!("ftp open {}", url).as_str(), None);
+        self.desktop_surface_status = alloc::format!("Conectando a {}...", url);
+    }
+
     fn cancel_desktop_create_folder(&mut self) {
+        let kind = self.desktop_create_folder.as_ref().map(|p| p.kind);
         self.desktop_create_folder = None;
-        self.desktop_surface_status = String::from("Crear carpeta cancelado.");
+        self.desktop_surface_status = if kind == Some(DesktopPromptKind::ConnectServer) {
+            String::from("Conectar a servidor cancelado.")
+        } else {
+            String::from("Crear carpeta cancelado.")
+        };
     }
 
     fn commit_desktop_create_folder(&mut self) {
         let Some(prompt) = self.desktop_create_folder.clone() else {
             return;
         };
+        if prompt.kind == DesktopPromptKind::ConnectServer {
+            self.commit_explorer_connect_server(prompt);
+            return;
+        }
         let name = prompt.input.trim();
         if name.is_empty() {
             self.desktop_surface_status = String::from("Nombre vacio. Escribe un nombre.");
@@ -16524,7 +17803,7 @@ impl Compositor {
         }
 
         let result = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             fat.ensure_subdirectory(prompt.dir_cluster, name)
         };
 
@@ -16575,7 +17854,11 @@ impl Compositor {
                     return true;
                 }
                 if let Some(prompt) = self.desktop_create_folder.as_mut() {
-                    if prompt.input.len() < 28 {
+                    let max_len = match prompt.kind {
+                        DesktopPromptKind::CreateFolder => 28,
+                        DesktopPromptKind::ConnectServer => 120,
+                    };
+                    if prompt.input.len() < max_len {
                         prompt.input.push(ch);
                     }
                 }
@@ -16638,6 +17921,18 @@ impl Compositor {
         };
 
         let rect = self.desktop_create_prompt_rect();
+        let (title, placeholder, hint) = match prompt.kind {
+            DesktopPromptKind::CreateFolder => (
+                "Crear carpeta",
+                "<nombre de carpeta>",
+                "Enter: crear  Esc: cancelar",
+            ),
+            DesktopPromptKind::ConnectServer => (
+                "Conectar a servidor",
+                "<ftp[s]://[usuario[:clave]@]host[/ruta]>",
+                "Enter: conectar  Esc: cancelar",
+            ),
+        };
 
         framebuffer::rect(
             rect.x.max(0) as usize,
@@ -16664,7 +17959,7 @@ impl Compositor {
         framebuffer::draw_text_5x7(
             (rect.x + 10).max(0) as usize,
             (rect.y + 12).max(0) as usize,
-            "Crear carpeta",
+            title,
             0xEAF6FF,
         );
         framebuffer::draw_text_5x7(
@@ -16690,7 +17985,10 @@ impl Compositor {
             0x4F7A9D,
         );
         let shown = if prompt.input.is_empty() {
-            "<nombre de carpeta>"
+            placeholder
+        } else if prompt.input.len() > 40 {
+            // Keep the end of long URLs (the part being typed) visible.
+            &prompt.input[prompt.input.len() - 40..]
         } else {
             prompt.input.as_str()
         };
@@ -16704,7 +18002,7 @@ impl Compositor {
         framebuffer::draw_text_5x7(
             (rect.x + 10).max(0) as usize,
             (rect.y + 86).max(0) as usize,
-            "Enter: crear  Esc: cancelar",
+            hint,
             0xA9C6E1,
         );
 
@@ -16917,7 +18215,7 @@ impl Compositor {
         }
 
         let result = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             let source_entry = if prompt.source_is_directory {
                 Self::find_directory_entry_by_hint(
                     fat,
@@ -17668,6 +18966,10 @@ impl Compositor {
             let max_y = self.height.saturating_sub(1) as i32;
             self.mouse_pos.x = self.mouse_pos.x.saturating_add(dx).clamp(0, max_x);
             self.mouse_pos.y = self.mouse_pos.y.saturating_add(dy).clamp(0, max_y);
+            if let Some((abs_x, abs_y)) = crate::input::take_absolute_pointer() {
+                self.mouse_pos.x = abs_x.clamp(0, max_x);
+                self.mouse_pos.y = abs_y.clamp(0, max_y);
+            }
             if modal {
                 let was_left_down = self.last_mouse_down;
                 self.last_mouse_down = left_btn;
@@ -17867,19 +19169,13 @@ impl Compositor {
         framebuffer::draw_text_5x7(
             (rect.x + 12).max(0) as usize,
             (rect.y + 86).max(0) as usize,
-            {
-                let done_mb_x10 = prompt.done_units.saturating_mul(10) / (1024 * 1024);
-                let total_mb_x10 = prompt.total_units.saturating_mul(10) / (1024 * 1024);
-                alloc::format!(
-                    "Items: {}/{}  Data: {}.{} / {}.{} MB",
-                    prompt.done_items,
-                    prompt.total_items,
-                    done_mb_x10 / 10,
-                    done_mb_x10 % 10,
-                    total_mb_x10 / 10,
-                    total_mb_x10 % 10
-                )
-            }
+            alloc::format!(
+                "Items: {}/{}  Data: {} / {}",
+                prompt.done_items,
+                prompt.total_items,
+                crate::i18n::format::size(prompt.done_units as u64),
+                crate::i18n::format::size(prompt.total_units as u64)
+            )
             .as_str(),
             0xA7CCE7,
         );
@@ -18283,7 +19579,7 @@ impl Compositor {
                 }
 
                 if !mounted_with_temp {
-                    let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                     let root_cluster = fat.root_cluster;
                     let volume_label = Self::volume_label_text(fat).unwrap_or(String::from("USB"));
                     let root_path = alloc::format!("{}/", volume_label);
@@ -18360,6 +19656,21 @@ impl Compositor {
                 }
             }
 
+            // Mounted volumes are save targets too; cluster 0 marks them.
+            for mount in crate::fs::vfs::volume_mounts() {
+                locations.push(NotepadSaveLocation {
+                    device_index: None,
+                    cluster: 0,
+                    path: alloc::format!("{}/", mount.path),
+                    label: alloc::format!("{} [{}]", mount.path, mount.kind.as_str()),
+                    is_unit: true,
+                    depth: 0,
+                    parent: None,
+                    expanded: false,
+                    has_children: false,
+                });
+            }
+
             let mut selected_index = 0usize;
             if current_cluster >= 2 {
                 if let Some(idx) = locations.iter().position(|loc| {
@@ -18375,6 +19686,25 @@ impl Compositor {
                         selected_index = idx;
                     }
                 }
+            } else if let Some(dir) = Self::mounted_dialog_dir(current_cluster, current_path.as_str()) {
+                let path = alloc::format!("{}/", dir);
+                selected_index = match locations.iter().position(|loc| loc.cluster == 0 && loc.path.eq_ignore_ascii_case(&path)) {
+                    Some(idx) => idx,
+                    None => {
+                        locations.push(NotepadSaveLocation {
+                            device_index: None,
+                            cluster: 0,
+                            path,
+                            label: dir,
+                            is_unit: true,
+                            depth: 0,
+                            parent: None,
+                            expanded: false,
+                            has_children: false,
+                        });
+                        locations.len() - 1
+                    }
+                };
             }
 
             (locations, selected_index)
@@ -18398,7 +19728,9 @@ impl Compositor {
             selected_index = 0;
         }
 
-        let has_real_target = locations.iter().any(|loc| loc.device_index.is_some() || loc.cluster >= 2);
+        let has_real_target = locations.iter().any(|loc| {
+            loc.device_index.is_some() || loc.cluster >= 2 || Self::mounted_dialog_dir(loc.cluster, &loc.path).is_some()
+        });
         let visible = self.notepad_save_prompt_visible_item_count();
         self.notepad_save_prompt = Some(NotepadSavePromptState {
             kind: SavePromptKind::Notepad,
@@ -18601,7 +19933,7 @@ impl Compositor {
                 }
 
                 if !mounted_with_temp {
-                    let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                     let root_cluster = fat.root_cluster;
                     let volume_label = Self::volume_label_text(fat).unwrap_or(String::from("USB"));
                     let root_path = alloc::format!("{}/", volume_label);
@@ -18817,7 +20149,7 @@ impl Compositor {
         };
 
         let (file_name, text) = match self.windows.iter().find(|w| w.id == prompt.win_id) {
-            Some(win) if win.is_notepad() => (win.notepad_file_name.clone(), win.notepad_text.clone()),
+            Some(win) if win.is_notepad() => (win.notepad_file_name.clone(), String::from(win.notepad_area.text())),
             _ => {
                 self.notepad_save_prompt = None;
                 return;
@@ -18855,6 +20187,24 @@ impl Compositor {
             return;
         }
 
+        if let Some(dir) = Self::mounted_dialog_dir(target_location.cluster, target_location.path.as_str()) {
+            let result = crate::fs::vfs::write(crate::fs::vfs::join(dir.as_str(), trimmed_name).as_str(), text.as_bytes());
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == prompt.win_id) {
+                match result {
+                    Ok(()) => {
+                        win.notepad_dir_cluster = 0;
+                        win.notepad_dir_path = target_location.path.clone();
+                        win.set_notepad_status(alloc::format!("File saved in {}.", target_location.path).as_str());
+                    }
+                    Err(e) => win.set_notepad_status(alloc::format!("Save failed: {}", e).as_str()),
+                }
+            }
+            if result.is_ok() {
+                self.notepad_save_prompt = None;
+            }
+            return;
+        }
+
         if !self.ensure_fat_ready_for_notepad(prompt.win_id) {
             return;
         }
@@ -18862,7 +20212,7 @@ impl Compositor {
         let selected_device_index = target_location.device_index;
         let mut switched_device = false;
         let save_result = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             let mut target_cluster = target_location.cluster;
             let mut target_path = target_location.path.clone();
             let mut mount_error: Option<String> = None;
@@ -20032,7 +21382,7 @@ impl Compositor {
         let mut resolved_cluster = source_dir_cluster;
         if resolved_cluster < 2 {
             if let Some(path) = source_dir_path {
-                let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 if let Some(cluster) =
                     Self::resolve_directory_cluster_from_explorer_path(fat, path)
                 {
@@ -20082,7 +21432,7 @@ impl Compositor {
             return false;
         }
 
-        let root_cluster = unsafe { crate::fat32::GLOBAL_FAT.root_cluster };
+        let root_cluster = unsafe { crate::fat32::GLOBAL_FAT.get().root_cluster };
         if source_dir_cluster == root_cluster
             && Self::is_quick_access_shortcut_name(item.label.as_str())
         {
@@ -20096,7 +21446,7 @@ impl Compositor {
         if source_dir_cluster < 2 {
             return false;
         }
-        let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+        let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
         fat.resolve_path(fat.root_cluster, "TRASH/")
             .map(|(_, c)| c == source_dir_cluster)
             .unwrap_or(false)
@@ -20677,6 +22027,9 @@ impl Compositor {
                         } else if idx == 1 {
                             label = "Crear nota";
                             bg = 0x3A4D66;
+                        } else if idx == item_count - 1 {
+                            label = "Conectar a servidor";
+                            bg = 0x2F4F5C;
                         } else if idx == 2 && menu.show_paste {
                             if let Some(clip) = self.explorer_clipboard.as_ref() {
                                 if clip.mode == ExplorerClipboardMode::Cut {
@@ -21118,6 +22471,18 @@ impl Compositor {
         false
     }
 
+    fn handle_notepad_selection_drag_update(&mut self, mouse_x: i32, mouse_y: i32) -> bool {
+        let Some(id) = self.notepad_selection_drag else {
+            return false;
+        };
+        if let Some(win) = self.windows.iter_mut().find(|w| w.id == id) {
+            let _ = win.notepad_drag_to(mouse_x, mouse_y);
+            return true;
+        }
+        self.notepad_selection_drag = None;
+        false
+    }
+
     fn handle_explorer_context_click(&mut self, win_id: usize, mouse_x: i32, mouse_y: i32) -> bool {
         let (clicked, is_canvas, source_dir_cluster, source_path, items) =
             match self.windows.iter().find(|w| w.id == win_id) {
@@ -21189,7 +22554,7 @@ impl Compositor {
         if is_canvas {
             self.explorer_clear_selection_scope(win_id, source_dir_cluster);
             let show_paste = self.explorer_clipboard.is_some();
-            let item_count = if show_paste { 3 } else { 2 };
+            let item_count = if show_paste { 4 } else { 3 }; // + Conectar a servidor
             let is_trash_dir = Self::explorer_source_is_trash_dir(source_dir_cluster);
             let (menu_x, menu_y) =
                 self.clamp_explorer_context_menu_origin(mouse_x, mouse_y, item_count);
@@ -21213,6 +22578,7 @@ impl Compositor {
     }
 
     fn handle_right_click(&mut self, mouse_x: i32, mouse_y: i32) {
+        self.window_context_menu = None;
         if self.notepad_save_prompt.is_some() {
             self.explorer_context_menu = None;
             self.desktop_context_menu = None;
@@ -21395,6 +22761,7 @@ impl Compositor {
                 self.explorer_context_menu = None;
                 self.desktop_context_menu = None;
                 self.ide_context_menu = None;
+                self.open_window_context_menu(top_idx, mouse_x, mouse_y);
                 return;
             }
         }
@@ -21443,7 +22810,7 @@ impl Compositor {
                 // Handle "Restaurar"
                 if let Some(item) = menu.target_item.as_ref() {
                     let targets = self.explorer_context_target_items(menu.win_id, menu.source_dir_cluster, item);
-                    let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                     let mut any_restore_failed = false;
 
                     for target in targets {
@@ -21504,7 +22871,7 @@ impl Compositor {
                 // Handle "Eliminar" (permanently delete from trash)
                 if let Some(item) = menu.target_item.as_ref() {
                     let targets = self.explorer_context_target_items(menu.win_id, menu.source_dir_cluster, item);
-                    let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                     for target in targets {
                         let loc_name = alloc::format!("{}.LOC", target.cluster);
                         if target.kind == ExplorerItemKind::Directory {
@@ -21594,7 +22961,7 @@ impl Compositor {
                     if let Some(item) = menu.target_item.as_ref() {
                         let is_trash_shortcut = item.kind == ExplorerItemKind::ShortcutRecycleBin;
                         if is_trash_shortcut && idx == 0 {
-                            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                             let trash_cluster = fat.resolve_path(fat.root_cluster, "TRASH/").map(|(_, c)| c).unwrap_or(0);
                             if trash_cluster >= 2 {
                                 let _ = fat.empty_directory(trash_cluster);
@@ -21652,6 +23019,8 @@ impl Compositor {
                         self.begin_explorer_create_folder(menu.win_id);
                     } else if idx == 1 {
                         self.open_notepad_blank();
+                    } else if idx == item_count - 1 {
+                        self.begin_explorer_connect_server(menu.win_id);
                     } else if idx == 2 && menu.show_paste {
                         self.paste_explorer_clipboard(menu.win_id);
                     }
@@ -21891,7 +23260,7 @@ impl Compositor {
                     if let Some(item) = menu.target_item.as_ref() {
                         let is_trash = item.kind == ExplorerItemKind::ShortcutRecycleBin;
                         if is_trash && idx == 0 {
-                            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                             let trash_cluster = fat.resolve_path(fat.root_cluster, "TRASH/").map(|(_, c)| c).unwrap_or(0);
                             if trash_cluster >= 2 {
                                 let _ = fat.empty_directory(trash_cluster);
@@ -21977,7 +23346,7 @@ impl Compositor {
                                         return true;
                                     }
                                     let (shortcut_name, result) = {
-                                        let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                                        let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                                         let shortcut_name = Self::desktop_unique_shortcut_name(
                                             fat,
                                             desktop_cluster,
@@ -22281,6 +23650,7 @@ impl Compositor {
             source_label: first.source_label.clone(),
             items: clip_items.clone(),
         });
+        crate::gui::clipboard::set(Payload::Files(Self::file_refs_from_clipboard(clip_items.as_slice())));
 
         let verb = if mode == ExplorerClipboardMode::Cut {
             "Cortar"
@@ -22409,6 +23779,7 @@ impl Compositor {
             source_label: first.source_label.clone(),
             items: clip_items.clone(),
         });
+        crate::gui::clipboard::set(Payload::Files(Self::file_refs_from_clipboard(clip_items.as_slice())));
 
         let verb = if mode == ExplorerClipboardMode::Cut {
             "Cortar"
@@ -22467,7 +23838,7 @@ impl Compositor {
 
         for item in files.into_iter() {
             let result = {
-                let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 let source_entry = match Self::find_file_entry_by_hint(
                     fat,
                     source_dir_cluster,
@@ -22517,7 +23888,7 @@ impl Compositor {
             }
 
             let result = {
-                let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 let source_entry = match Self::find_directory_entry_by_hint(
                     fat,
                     source_dir_cluster,
@@ -23709,7 +25080,7 @@ impl Compositor {
             return Ok((total_units, total_items));
         }
 
-        let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+        let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
         let source_dir_cluster = Self::resolve_directory_cluster_from_explorer_path(
             fat,
             clip.source_dir_path.as_str(),
@@ -23860,7 +25231,7 @@ impl Compositor {
             .map_err(|e| alloc::format!("Pegar cancelado: {}", e))?;
 
         if !cross_device && clip.source_is_directory && clip.source_item_cluster >= 2 {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             if Self::directory_is_descendant_of(fat, clip.source_item_cluster, dst_dir_cluster) {
                 return Err(String::from(
                     "Pegar error: no puedes pegar una carpeta dentro de si misma.",
@@ -23896,7 +25267,7 @@ impl Compositor {
                     let source_fs_name = Self::dir_entry_fs_name(&src_fat, &source_entry);
                     let source_name_for_target =
                         Self::dir_entry_target_name(&source_entry, clip.source_label.as_str());
-                    let dst_fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                    let dst_fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                     let target_name = if clip.mode == ExplorerClipboardMode::Copy {
                         Self::ensure_copy_name_available_in_dir(
                             dst_fat,
@@ -23964,7 +25335,7 @@ impl Compositor {
                 let source_fs_name = Self::dir_entry_fs_name(&src_fat, &source_entry);
                 let source_name_for_target =
                     Self::dir_entry_target_name(&source_entry, clip.source_label.as_str());
-                let dst_fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let dst_fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 let target_name = if clip.mode == ExplorerClipboardMode::Copy {
                     Self::ensure_copy_name_available_in_dir(
                         dst_fat,
@@ -24008,7 +25379,7 @@ impl Compositor {
             })()
         } else {
             (|| -> Result<(String, bool, usize, usize, usize, bool, Option<String>), String> {
-                let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 let source_dir_cluster = Self::resolve_directory_cluster_from_explorer_path(
                     fat,
                     clip.source_dir_path.as_str(),
@@ -24690,7 +26061,7 @@ impl Compositor {
         };
 
         let deleted_name = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             let source_entry = match Self::find_file_entry_by_hint(
                 fat,
                 source_dir_cluster,
@@ -24773,7 +26144,7 @@ impl Compositor {
         };
 
         let deleted_name = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             let source_entry = match Self::find_directory_entry_by_hint(
                 fat,
                 source_dir_cluster,
@@ -24850,7 +26221,7 @@ impl Compositor {
         }
 
         let deleted_name = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             let source_entry = match Self::find_file_entry_by_hint(
                 fat,
                 source_dir_cluster,
@@ -24920,7 +26291,7 @@ impl Compositor {
         }
 
         let deleted_name = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             let source_entry = match Self::find_directory_entry_by_hint(
                 fat,
                 source_dir_cluster,
@@ -24982,7 +26353,7 @@ impl Compositor {
         source_dir_cluster: u32,
         item: &ExplorerItem,
     ) -> Result<(String, usize, usize, usize), String> {
-        let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+        let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
         let source_entry = Self::find_file_entry_by_hint(
             fat,
             source_dir_cluster,
@@ -25289,6 +26660,16 @@ impl Compositor {
 
     fn update_pointer_capture(&mut self, mouse_x: i32, mouse_y: i32, left_down: bool) -> bool {
         if !left_down {
+            // A window dropped on a workspace cell in the taskbar moves there.
+            if let Some(WindowPointerCapture::Move(c)) = self.pointer_capture.take() {
+                let rel = Point {
+                    x: mouse_x - self.taskbar.rect.x,
+                    y: mouse_y - self.taskbar.rect.y,
+                };
+                if let Some(idx) = self.taskbar_workspace_hit_test(rel) {
+                    return self.move_window_to_desktop(c.win_id, idx);
+                }
+            }
             self.pointer_capture = None;
             return false;
         }
@@ -25370,6 +26751,7 @@ impl Compositor {
             return;
         }
         // Keep background runtimes advancing even when there is no repaint yet.
+        self.service_desktop_theme();
         self.service_clipboard_paste_job();
         self.service_install_tasks();
         self.service_pending_terminal_commands();
@@ -25379,9 +26761,188 @@ impl Compositor {
         self.service_browser_litehtmlrt_surface();
         self.service_browser_servort_surface();
         self.service_linux_bridge_window();
+        self.service_process_env();
         self.service_terminal_streams();
+        self.service_pending_fetches();
+        self.service_browser_loads();
+        self.service_terminal_net_jobs();
+        self.service_ide_json_fetches();
+        self.service_captive_portal();
+        self.service_usb_hotplug();
+        self.service_thunderbolt();
         self.service_video_player_windows();
         self.service_task_manager_windows();
+        self.service_caret_blink();
+        self.service_osk();
+        self.service_display_color();
+        self.service_clock_applets();
+        self.service_audio_capture();
+        self.service_search_index();
+        self.service_fs_watch();
+    }
+
+    /// Let the file index walk a few more folders, unless a copy or the
+    /// Linux runloop needs the disk.
+    fn service_search_index(&mut self) {
+        if self.io_background_pause_for_linux || self.clipboard_paste_job.is_some() {
+            return;
+        }
+        crate::search_index::service();
+    }
+
+    /// List again the Explorer folders that changed on the mounted volume,
+    /// so files written by the terminal, apps or ring 3 appear without F5.
+    /// Windows on other drives are left alone; their copies refresh them.
+    /// Also writes the link/xattr sidecar after renames and deletes.
+    fn service_fs_watch(&mut self) {
+        const OWNER: &str = "explorador";
+        if self.io_background_pause_for_linux || self.clipboard_paste_job.is_some() {
+            return;
+        }
+        crate::fsmeta::flush_pending();
+        let (volume, root) = {
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get() };
+            if fat.init_status != crate::fat32::InitStatus::Success {
+                return;
+            }
+            (fat.volume_id(), fat.root_cluster)
+        };
+        let watch_id = match self.fs_watch {
+            Some((watched, id)) if watched == volume => id,
+            stale => {
+                if let Some((_, id)) = stale {
+                    let _ = crate::fswatch::remove_watch(OWNER, id);
+                }
+                self.fs_watch = None;
+                let Ok(id) = crate::fswatch::add_watch(OWNER, volume, None) else {
+                    return;
+                };
+                self.fs_watch = Some((volume, id));
+                id
+            }
+        };
+        let Ok(events) = crate::fswatch::read_events(OWNER, watch_id, crate::fswatch::QUEUE_MAX) else {
+            return;
+        };
+        if events.is_empty() {
+            return;
+        }
+        let overflow = events.iter().any(|e| e.change == crate::fswatch::Change::Overflow);
+        let mut refresh = Vec::new();
+        for win in self.windows.iter() {
+            if !win.is_explorer() || win.explorer_search_active {
+                continue;
+            }
+            if win.explorer_device_index.is_some() && win.explorer_device_index != self.current_volume_device_index {
+                continue;
+            }
+            let cluster = if win.explorer_current_cluster < 2 {
+                root
+            } else {
+                win.explorer_current_cluster
+            };
+            if overflow || events.iter().any(|e| e.dir == cluster) {
+                refresh.push((
+                    win.id,
+                    win.explorer_current_cluster,
+                    win.explorer_path.clone(),
+                    win.explorer_status.clone(),
+                    win.explorer_device_index,
+                ));
+            }
+        }
+        for (id, cluster, path, status, device_index) in refresh.into_iter() {
+            self.show_explorer_directory(id, cluster, path, status, device_index);
+        }
+    }
+
+    /// Ring due alarms, timers and calendar events, and tick open clock
+    /// windows once a second.
+    fn service_clock_applets(&mut self) {
+        let mut dirty = crate::alarm::service();
+        for win in self.windows.iter_mut().filter(|w| w.is_clock()) {
+            dirty |= win.clock_refresh();
+        }
+        if dirty {
+            self.mark_dirty();
+        }
+    }
+
+    /// Keep the microphone open while a Settings or recorder window shows
+    /// its level meter, and redraw those windows as the level moves.
+    fn service_audio_capture(&mut self) {
+        const OWNER: &str = "medidor";
+        let wanted = crate::capture::available()
+            && self
+                .windows
+                .iter()
+                .any(|w| w.is_recorder() || w.kind == WindowKind::Settings);
+        match (wanted, self.capture_meter) {
+            (true, None) => self.capture_meter = crate::capture::open(OWNER).ok(),
+            (false, Some(id)) => {
+                let _ = crate::capture::close(OWNER, id);
+                self.capture_meter = None;
+            }
+            _ => {}
+        }
+        let mut dirty = false;
+        for win in self.windows.iter_mut() {
+            dirty |= win.recorder_refresh();
+            dirty |= win.settings_level_refresh();
+        }
+        if dirty {
+            self.mark_dirty();
+        }
+    }
+
+    /// Follow gamma / night light changes; open Settings windows show the
+    /// current values.
+    fn service_display_color(&mut self) {
+        if !crate::gamma::service() {
+            return;
+        }
+        for win in self.windows.iter_mut().filter(|w| w.is_settings()) {
+            win.render_settings();
+        }
+        self.mark_dirty();
+    }
+
+    /// Summon or dismiss the on-screen keyboard as text windows gain and
+    /// lose the focus, and run its long-press timer.
+    fn service_osk(&mut self) {
+        let text_focus = self.active_window_id.is_some_and(|id| {
+            self.windows.iter().any(|w| {
+                w.id == id
+                    && w.state != WindowState::Minimized
+                    && (w.is_terminal()
+                        || w.is_notepad()
+                        || w.is_browser()
+                        || w.is_ide_studio()
+                        || w.is_search()
+                        || w.is_mail())
+            })
+        });
+        if crate::gui::osk::service(text_focus, crate::timer::now_ms()) {
+            self.mark_dirty();
+        }
+    }
+
+    /// Redraw the focused terminal when the caret blink phase flips.
+    fn service_caret_blink(&mut self) {
+        if !crate::gui::caret::take_changed() {
+            return;
+        }
+        let Some(active_id) = self.active_window_id else {
+            return;
+        };
+        if let Some(win) = self
+            .windows
+            .iter_mut()
+            .find(|w| w.id == active_id && w.kind == WindowKind::Terminal)
+        {
+            win.render_terminal();
+            self.mark_dirty();
+        }
     }
 
     #[inline]
@@ -25432,18 +26993,45 @@ impl Compositor {
         crate::framebuffer::clear(0x000000);
         framebuffer::present();
 
-        match crate::acpi::try_suspend_to_ram() {
-            Ok(()) => {
+        match crate::pm::sleep(crate::pm::sleep_mode()) {
+            Ok(crate::pm::Slept::Resumed) => {
                 let _ = crate::restore_gui_after_s3_resume();
                 self.wake_from_soft_suspend();
             }
-            Err(_) => {
+            Ok(crate::pm::Slept::Idle) => {
                 self.enter_soft_suspend();
             }
+            Err(err) => {
+                crate::gui::notifications::post(
+                    "power",
+                    "No se pudo suspender",
+                    err.as_str(),
+                    crate::gui::notifications::Urgency::Warning,
+                );
+                self.needs_repaint = true;
+            }
+        }
+    }
+
+    /// Lid switch and ACPI buttons: closing the lid or the sleep button
+    /// suspends, opening it or the power button wakes from s2idle.
+    pub fn service_power_events(&mut self) {
+        while let Some(event) = crate::pm::poll_events() {
+            match event {
+                crate::pm::PowerEvent::LidClosed if crate::pm::lid_suspends() && !self.is_suspended => {
+                    self.enter_suspend();
+                }
+                crate::pm::PowerEvent::SleepButton if !self.is_suspended => self.enter_suspend(),
+                crate::pm::PowerEvent::LidOpened | crate::pm::PowerEvent::PowerButton if self.is_suspended => {
+                    self.wake_from_soft_suspend();
+                }
+                _ => {}
+            }
         }
     }
 
     fn wake_from_soft_suspend(&mut self) {
+        crate::pm::resume_devices();
         self.is_suspended = false;
         self.suspend_ignore_mouse_until_release = false;
         self.needs_repaint = true;
@@ -25470,6 +27058,844 @@ impl Compositor {
         match event {
             Event::Keyboard(_) => true,
             Event::Mouse(m) => self.suspended_mouse_should_wake(m),
+            Event::Gamepad(g) => matches!(g.kind, crate::gamepad::GamepadEventKind::ButtonDown(_)),
+        }
+    }
+
+    /// Queue the event on the focused window and, through the gamepad key
+    /// map, feed it as a key press to windows that only read the keyboard.
+    fn handle_gamepad_event(&mut self, event: crate::gamepad::GamepadEvent) {
+        if let Some(active_id) = self.active_window_id {
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == active_id) {
+                win.push_gamepad_event(event);
+            }
+        }
+        if let Some(key) = crate::gamepad::keyboard_event(&event) {
+            self.handle_event(Event::Keyboard(key));
+        }
+    }
+
+    /// Hand everything `event_queue::pump` collected to `handle_event`.
+    pub fn drain_input_events(&mut self) {
+        while let Some(entry) = crate::gui::event_queue::pop() {
+            self.event_time_us = entry.at_us;
+            match entry.kind {
+                crate::gui::event_queue::EntryKind::Gui(event) => self.handle_event(event),
+                crate::gui::event_queue::EntryKind::TogglePerfHud => self.toggle_perf_hud(),
+                crate::gui::event_queue::EntryKind::QuickSearch => self.toggle_quick_search(),
+                crate::gui::event_queue::EntryKind::Workspace { index, move_window } => {
+                    self.handle_workspace_key(index, move_window)
+                }
+            }
+        }
+    }
+
+    /// Let `win_id` offer `payload` for dragging from the press at `(x, y)`.
+    /// Nothing happens unless the pointer then moves past the drag threshold.
+    fn offer_drag(&mut self, win_id: usize, payload: Payload, x: i32, y: i32) {
+        self.drag_session = Some(DragSession::new(win_id, payload, Point { x, y }));
+    }
+
+    /// Topmost visible window under the pointer that takes `kind`.
+    fn drop_target_at(&self, x: i32, y: i32, kind: PayloadKind) -> Option<usize> {
+        let p = Point { x, y };
+        self.windows
+            .iter()
+            .rev()
+            .filter(|w| self.window_on_active_desktop(w))
+            .filter(|w| w.state == WindowState::Normal || w.state == WindowState::Maximized)
+            .find(|w| w.rect.contains(p))
+            .filter(|w| w.accepts_drop(kind))
+            .map(|w| w.id)
+    }
+
+    fn send_drag_event(&mut self, win_id: usize, phase: DragPhase, kind: PayloadKind, x: i32, y: i32) {
+        if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+            win.push_drag_event(DragEvent { phase, kind, x, y });
+        }
+    }
+
+    /// Advance the drag session. Returns true while a drag owns the pointer.
+    fn update_drag_session(&mut self, mouse_x: i32, mouse_y: i32, left_down: bool) -> bool {
+        let Some(mut session) = self.drag_session.take() else {
+            return false;
+        };
+        let kind = session.payload.kind();
+        let target = self
+            .drop_target_at(mouse_x, mouse_y, kind)
+            .filter(|&id| id != session.source_win);
+        match session.update(Point { x: mouse_x, y: mouse_y }, left_down, target) {
+            DragStep::Pending => {
+                self.drag_session = Some(session);
+                false
+            }
+            DragStep::Released => false,
+            DragStep::Started => {
+                self.send_drag_event(session.source_win, DragPhase::Start, kind, mouse_x, mouse_y);
+                self.drag_session = Some(session);
+                true
+            }
+            DragStep::Moved { left, entered } => {
+                if let Some(id) = left {
+                    self.send_drag_event(id, DragPhase::Leave, kind, mouse_x, mouse_y);
+                }
+                if let Some(id) = entered.or(session.over_win()) {
+                    self.send_drag_event(id, DragPhase::Over, kind, mouse_x, mouse_y);
+                }
+                self.drag_session = Some(session);
+                true
+            }
+            DragStep::Dropped { target } => {
+                match target {
+                    Some(id) => {
+                        self.send_drag_event(id, DragPhase::Drop, kind, mouse_x, mouse_y);
+                        self.deliver_drop(id, session.payload);
+                    }
+                    None => self.send_drag_event(session.source_win, DragPhase::Cancel, kind, mouse_x, mouse_y),
+                }
+                true
+            }
+        }
+    }
+
+    fn deliver_drop(&mut self, win_id: usize, payload: Payload) {
+        let Some(kind) = self.windows.iter().find(|w| w.id == win_id).map(|w| w.kind) else {
+            return;
+        };
+        self.active_window_id = Some(win_id);
+        match (kind, payload) {
+            (WindowKind::Explorer, Payload::Files(files)) => self.drop_files_on_explorer(win_id, files),
+            (WindowKind::Notepad, Payload::Files(files)) => {
+                let Some(file) = files.into_iter().find(|f| !f.is_directory) else {
+                    return;
+                };
+                let item = ExplorerItem::new(file.name.as_str(), ExplorerItemKind::File, file.cluster, file.size);
+                let (text, status) = self.read_notepad_text(&item);
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    win.load_notepad_document(
+                        file.dir_cluster,
+                        file.dir_path.as_str(),
+                        file.name.as_str(),
+                        text.as_str(),
+                        status.as_str(),
+                    );
+                }
+            }
+            (_, payload) => {
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    win.insert_text(payload.as_text().as_str());
+                }
+            }
+        }
+    }
+
+    /// Copy dropped files into the folder open in `win_id`, through the same
+    /// background job as paste. The explorer's own clipboard is left as it was.
+    fn drop_files_on_explorer(&mut self, win_id: usize, files: Vec<FileRef>) {
+        let dst_cluster = self.windows.iter().find(|w| w.id == win_id).map(|w| w.explorer_current_cluster);
+        let items: Vec<ExplorerClipboardItem> = files
+            .iter()
+            .filter(|f| Some(f.dir_cluster) != dst_cluster)
+            .map(|f| ExplorerClipboardItem {
+                source_device_index: f.device_index,
+                source_dir_cluster: f.dir_cluster,
+                source_dir_path: f.dir_path.clone(),
+                source_item_cluster: f.cluster,
+                source_is_directory: f.is_directory,
+                source_label: f.name.clone(),
+            })
+            .collect();
+        let Some(first) = items.first().cloned() else {
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                win.set_explorer_status("Soltar: los elementos ya estan en esta carpeta.");
+            }
+            return;
+        };
+        let previous = self.explorer_clipboard.take();
+        self.explorer_clipboard = Some(ExplorerClipboardState {
+            mode: ExplorerClipboardMode::Copy,
+            source_device_index: first.source_device_index,
+            source_dir_cluster: first.source_dir_cluster,
+            source_dir_path: first.source_dir_path.clone(),
+            source_item_cluster: first.source_item_cluster,
+            source_is_directory: first.source_is_directory,
+            source_label: first.source_label.clone(),
+            items,
+        });
+        self.paste_explorer_clipboard(win_id);
+        self.explorer_clipboard = previous;
+    }
+
+    fn file_refs_from_clipboard(items: &[ExplorerClipboardItem]) -> Vec<FileRef> {
+        items
+            .iter()
+            .map(|item| FileRef {
+                device_index: item.source_device_index,
+                dir_cluster: item.source_dir_cluster,
+                dir_path: item.source_dir_path.clone(),
+                cluster: item.source_item_cluster,
+                size: 0,
+                is_directory: item.source_is_directory,
+                name: item.source_label.clone(),
+            })
+            .collect()
+    }
+
+    fn open_window_context_menu(&mut self, win_idx: usize, mouse_x: i32, mouse_y: i32) -> bool {
+        let items = self.windows[win_idx].context_menu_items();
+        if items.is_empty() {
+            return false;
+        }
+        let bounds = Rect::new(0, 0, self.width as u32, self.taskbar.rect.y.max(0) as u32);
+        self.window_context_menu = Some(ContextMenu::open(
+            self.windows[win_idx].id,
+            items,
+            Point { x: mouse_x, y: mouse_y },
+            bounds,
+        ));
+        true
+    }
+
+    fn handle_window_context_menu_left_click(&mut self, mouse_x: i32, mouse_y: i32) -> bool {
+        let Some(menu) = self.window_context_menu.take() else {
+            return false;
+        };
+        let p = Point { x: mouse_x, y: mouse_y };
+        if !menu.rect().contains(p) {
+            // Clicking elsewhere only closes the menu; the click still goes through.
+            return false;
+        }
+        if let Some(action) = menu.action_at(p) {
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == menu.win_id) {
+                win.apply_context_action(action);
+            }
+        }
+        true
+    }
+
+    fn draw_window_context_menu_overlay(&mut self) {
+        if let Some(menu) = self.window_context_menu.as_ref() {
+            menu.draw(self.mouse_pos);
+        }
+    }
+
+    fn draw_drag_overlay(&mut self) {
+        let Some(session) = self.drag_session.as_ref().filter(|s| s.is_active()) else {
+            return;
+        };
+        if let Some(win) = session.over_win().and_then(|id| self.windows.iter().find(|w| w.id == id)) {
+            let r = win.rect;
+            let (x, y) = (r.x.max(0) as usize, r.y.max(0) as usize);
+            framebuffer::rect(x, y, r.width as usize, 2, 0x38BDF8);
+            framebuffer::rect(x, y + r.height as usize - 2, r.width as usize, 2, 0x38BDF8);
+            framebuffer::rect(x, y, 2, r.height as usize, 0x38BDF8);
+            framebuffer::rect(x + r.width as usize - 2, y, 2, r.height as usize, 0x38BDF8);
+        }
+        let label = Self::trim_ascii_line(session.payload.summary().as_str(), 28);
+        let x = (self.mouse_pos.x + 14).max(0) as usize;
+        let y = (self.mouse_pos.y + 14).max(0) as usize;
+        let w = label.len() * 6 + 12;
+        framebuffer::rect(x, y, w, 16, 0x1D2D3D);
+        framebuffer::rect(x, y, w, 1, 0x5F85A8);
+        framebuffer::draw_text_5x7(x + 6, y + 5, label.as_str(), 0xEAF6FF);
+    }
+
+    fn dialog_bounds(&self) -> Rect {
+        Rect::new(0, 0, self.width as u32, self.taskbar.rect.y.max(0) as u32)
+    }
+
+    fn open_dialog(&mut self, dialog: Dialog, purpose: DialogPurpose) {
+        self.window_context_menu = None;
+        self.drag_session = None;
+        self.modal_dialog = Some(ModalDialog { dialog, purpose });
+    }
+
+    fn show_notice(&mut self, title: &str, text: &str) {
+        self.open_dialog(Dialog::message(title, text, &["Aceptar"]), DialogPurpose::Notice);
+    }
+
+    /// File dialog starting in one of the named root folders (Documents,
+    /// Downloads, Images...), or the volume root when that cannot be resolved.
+    fn open_file_dialog(
+        &mut self,
+        title: &str,
+        mode: FileDialogMode,
+        folder: &str,
+        default_name: &str,
+        extension: Option<&'static str>,
+        purpose: DialogPurpose,
+    ) {
+        let start = match self.resolve_named_root_dir_on_best_volume_with_index(folder, true, true) {
+            Ok((cluster, path, device_index)) => Some((cluster, path, Some(device_index))),
+            Err(_) if self.ensure_fat_ready() => {
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+                Some((fat.root_cluster, String::from("/"), self.current_volume_device_index))
+            }
+            Err(_) => None,
+        };
+        let Some((cluster, path, device_index)) = start else {
+            self.show_notice(title, "No hay volumen FAT32/exFAT disponible.");
+            return;
+        };
+        let dialog = Dialog::file(title, mode, device_index, cluster, path.as_str(), default_name, extension);
+        self.open_dialog(dialog, purpose);
+        self.load_dialog_listing(cluster, path);
+    }
+
+    fn set_dialog_status(&mut self, status: &str) {
+        if let Some(browser) = self.modal_dialog.as_mut().and_then(|m| m.dialog.file_browser_mut()) {
+            browser.status = String::from(status);
+        }
+    }
+
+    fn load_dialog_listing(&mut self, cluster: u32, path: String) {
+        let device_index = self
+            .modal_dialog
+            .as_ref()
+            .and_then(|m| m.dialog.file_browser())
+            .and_then(|b| b.device_index);
+        let ready = match device_index {
+            Some(index) => self.force_mount_volume_index(index),
+            None => self.ensure_fat_ready(),
+        };
+        if !ready {
+            self.set_dialog_status("No se pudo montar la unidad.");
+            return;
+        }
+
+        let listing = if cluster == 0 {
+            crate::fs::vfs::read_dir(Self::dialog_vfs_path(path.as_str()).as_str()).map(|entries| {
+                entries
+                    .into_iter()
+                    .map(|e| FileEntry {
+                        name: e.name,
+                        cluster: 0,
+                        size: e.size.min(u32::MAX as u64) as u32,
+                        is_directory: e.is_dir,
+                    })
+                    .collect::<Vec<FileEntry>>()
+            })
+        } else {
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+            let root = fat.root_cluster;
+            fat.read_dir_entries(cluster).map(|entries| {
+                let mut entries: Vec<FileEntry> = entries
+                    .iter()
+                    .filter(|e| e.valid)
+                    .map(|e| {
+                        let is_directory = e.file_type == crate::fs::FileType::Directory;
+                        FileEntry {
+                            name: e.full_name(),
+                            cluster: if is_directory && e.cluster == 0 { root } else { e.cluster },
+                            size: e.size,
+                            is_directory,
+                        }
+                    })
+                    .collect();
+                // Mounted volumes show up as folders at the root; cluster 0 marks them.
+                if cluster == root {
+                    for mount in crate::fs::vfs::volume_mounts() {
+                        if let Some(name) = mount.path.strip_prefix('/').filter(|name| !name.contains('/')) {
+                            entries.retain(|e| !e.name.eq_ignore_ascii_case(name));
+                            entries.push(FileEntry {
+                                name: String::from(name),
+                                cluster: 0,
+                                size: 0,
+                                is_directory: true,
+                            });
+                        }
+                    }
+                }
+                entries
+            })
+        };
+        let Some(browser) = self.modal_dialog.as_mut().and_then(|m| m.dialog.file_browser_mut()) else {
+            return;
+        };
+        match listing {
+            Ok(entries) => browser.set_listing(cluster, path.as_str(), entries),
+            Err(err) => browser.status = alloc::format!("No se pudo leer la carpeta: {}", err),
+        }
+    }
+
+    /// Namespace path of a dialog folder. Dialog paths start at `/` or with
+    /// the volume label (`LABEL/Documents/`); folders on mounted volumes have
+    /// cluster 0 and are reached through `crate::fs::vfs`.
+    fn dialog_vfs_path(path: &str) -> String {
+        let rel = if path.starts_with('/') {
+            path
+        } else {
+            path.split_once('/').map(|(_, rest)| rest).unwrap_or("")
+        };
+        crate::fs::vfs::normalize(rel)
+    }
+
+    /// Namespace path of a dialog or notepad folder when it is on a mounted volume.
+    fn mounted_dialog_dir(dir_cluster: u32, dir_path: &str) -> Option<String> {
+        let path = Self::dialog_vfs_path(dir_path);
+        (dir_cluster == 0 && crate::fs::vfs::is_mounted_path(path.as_str())).then_some(path)
+    }
+
+    /// Keys go to the open dialog and nowhere else.
+    fn handle_modal_dialog_key(&mut self, key: Option<char>, special: Option<SpecialKey>, down: bool) -> bool {
+        let bounds = self.dialog_bounds();
+        let Some(modal) = self.modal_dialog.as_mut() else {
+            return false;
+        };
+        if down {
+            let outcome = modal.dialog.key(key, special, bounds);
+            self.apply_dialog_outcome(outcome);
+        }
+        true
+    }
+
+    fn handle_modal_dialog_mouse(&mut self, mouse_x: i32, mouse_y: i32, wheel_delta: i32, is_new_left_click: bool) {
+        let bounds = self.dialog_bounds();
+        let double = self.clicks.count() >= 2;
+        let Some(modal) = self.modal_dialog.as_mut() else {
+            return;
+        };
+        if wheel_delta != 0 {
+            modal.dialog.wheel(wheel_delta, bounds);
+        }
+        if is_new_left_click {
+            let outcome = modal.dialog.click(Point { x: mouse_x, y: mouse_y }, bounds, double);
+            self.apply_dialog_outcome(outcome);
+        }
+    }
+
+    fn apply_dialog_outcome(&mut self, outcome: DialogOutcome) {
+        match outcome {
+            DialogOutcome::None => {}
+            DialogOutcome::Browse { cluster, path } => self.load_dialog_listing(cluster, path),
+            outcome => {
+                if let Some(modal) = self.modal_dialog.take() {
+                    self.finish_dialog(modal.purpose, outcome);
+                }
+            }
+        }
+    }
+
+    fn finish_dialog(&mut self, purpose: DialogPurpose, outcome: DialogOutcome) {
+        match (purpose, outcome) {
+            (DialogPurpose::NotepadOpen(win_id), DialogOutcome::File(choice)) => {
+                self.open_notepad_choice(win_id, choice)
+            }
+            (DialogPurpose::NotepadDelete(win_id), DialogOutcome::Button(0)) => self.delete_notepad_file(win_id),
+            (
+                purpose @ (DialogPurpose::BrowserDownload { .. } | DialogPurpose::SaveScreenshot(_)),
+                DialogOutcome::File(choice),
+            ) => {
+                if choice.exists {
+                    let text = alloc::format!("{} ya existe. Quieres reemplazarlo?", choice.name);
+                    self.open_dialog(
+                        Dialog::confirm("Reemplazar archivo", text.as_str(), "Reemplazar"),
+                        DialogPurpose::ConfirmReplace(Box::new(purpose), choice),
+                    );
+                } else {
+                    self.save_dialog_choice(purpose, choice);
+                }
+            }
+            (DialogPurpose::ConfirmReplace(purpose, choice), DialogOutcome::Button(0)) => {
+                self.save_dialog_choice(*purpose, choice)
+            }
+            (DialogPurpose::Print(win_id), DialogOutcome::Print { printer, copies }) => {
+                self.print_window(win_id, printer, copies)
+            }
+            (DialogPurpose::RestoreSession(windows), DialogOutcome::Button(0)) => {
+                let _ = self.restore_session(windows);
+            }
+            (DialogPurpose::RestoreSession(_), _) => {
+                let _ = crate::gui::session::clear();
+            }
+            (DialogPurpose::MountUsb(handle), DialogOutcome::Button(0)) => match crate::usb::volume_index(handle) {
+                Some(index) => self.open_desktop_disk_in_explorer(index),
+                None => self.show_notice("Unidad USB", "La unidad ya no esta conectada."),
+            },
+            (DialogPurpose::AuthorizeThunderbolt(port), DialogOutcome::Button(0)) => {
+                match crate::thunderbolt::authorize(port) {
+                    Ok(drivers) if drivers.is_empty() => {
+                        self.show_notice("Thunderbolt", "Dispositivo autorizado; ningun driver lo reclama.")
+                    }
+                    Ok(drivers) => {
+                        let text = alloc::format!("Dispositivo autorizado: {}.", drivers.join(", "));
+                        self.show_notice("Thunderbolt", text.as_str());
+                    }
+                    Err(e) => self.show_notice("Thunderbolt", e),
+                }
+            }
+            (DialogPurpose::AuthorizeThunderbolt(port), _) => {
+                let _ = crate::thunderbolt::deny(port);
+            }
+            _ => {}
+        }
+    }
+
+    fn save_dialog_choice(&mut self, purpose: DialogPurpose, choice: FileChoice) {
+        match purpose {
+            DialogPurpose::BrowserDownload { win_id, url } => self.start_browser_download(win_id, url, choice),
+            DialogPurpose::SaveScreenshot(bmp) => self.write_screenshot(bmp, choice),
+            _ => {}
+        }
+    }
+
+    fn draw_modal_dialog_overlay(&mut self) {
+        let bounds = self.dialog_bounds();
+        if let Some(modal) = self.modal_dialog.as_ref() {
+            modal.dialog.draw(bounds, self.mouse_pos);
+        }
+    }
+
+    /// Super+Space: show the quick search overlay, or hide it when open.
+    pub fn toggle_quick_search(&mut self) {
+        if self.quick_search.take().is_none() {
+            if self.modal_dialog.is_some() {
+                return;
+            }
+            self.taskbar.start_menu_open = false;
+            self.start_tools_open = false;
+            self.start_games_open = false;
+            self.start_apps_open = false;
+            self.window_context_menu = None;
+            self.drag_session = None;
+            let mut search = QuickSearch::new();
+            search.set_results(Vec::new(), Self::quick_search_status().as_str());
+            self.quick_search = Some(search);
+        }
+        self.needs_repaint = true;
+    }
+
+    fn quick_search_status() -> String {
+        let status = crate::search_index::status();
+        if status.folders == 0 {
+            String::from("Apps y ajustes (sin volumen indexado)")
+        } else if status.building {
+            alloc::format!("Indexando archivos... {} encontrados", status.records)
+        } else {
+            alloc::format!("{} archivos y carpetas indexados", status.records)
+        }
+    }
+
+    fn refresh_quick_search(&mut self) {
+        let Some(query) = self.quick_search.as_ref().map(|q| q.query.clone()) else {
+            return;
+        };
+        let results = self.quick_search_results(query.as_str());
+        let status = Self::quick_search_status();
+        if let Some(search) = self.quick_search.as_mut() {
+            search.set_results(results, status.as_str());
+        }
+    }
+
+    /// While the overlay is open it takes every key.
+    fn handle_quick_search_key(&mut self, key: Option<char>, special: Option<SpecialKey>, down: bool) -> bool {
+        let Some(search) = self.quick_search.as_mut() else {
+            return false;
+        };
+        if down {
+            let outcome = search.key(key, special);
+            self.apply_quick_search_outcome(outcome);
+        }
+        true
+    }
+
+    fn handle_quick_search_click(&mut self, mouse_x: i32, mouse_y: i32) {
+        let bounds = self.dialog_bounds();
+        let Some(search) = self.quick_search.as_mut() else {
+            return;
+        };
+        let outcome = search.click(Point { x: mouse_x, y: mouse_y }, bounds);
+        self.apply_quick_search_outcome(outcome);
+    }
+
+    fn apply_quick_search_outcome(&mut self, outcome: QuickSearchOutcome) {
+        match outcome {
+            QuickSearchOutcome::None => {}
+            QuickSearchOutcome::QueryChanged => self.refresh_quick_search(),
+            QuickSearchOutcome::Open(index) => {
+                let chosen = self.quick_search.take().and_then(|search| search.result(index).cloned());
+                if let Some(entry) = chosen {
+                    self.launch_start_app_shortcut(&StartAppShortcut {
+                        label: entry.label,
+                        command: entry.command,
+                    });
+                }
+            }
+            QuickSearchOutcome::Close => self.quick_search = None,
+        }
+        self.needs_repaint = true;
+    }
+
+    fn draw_quick_search_overlay(&mut self) {
+        let bounds = self.dialog_bounds();
+        if let Some(search) = self.quick_search.as_ref() {
+            search.draw(bounds, self.mouse_pos);
+        }
+    }
+
+    fn begin_notepad_open_dialog(&mut self, win_id: usize) {
+        self.open_file_dialog(
+            "Abrir archivo",
+            FileDialogMode::Open,
+            "Documents",
+            "",
+            None,
+            DialogPurpose::NotepadOpen(win_id),
+        );
+    }
+
+    fn open_notepad_choice(&mut self, win_id: usize, choice: FileChoice) {
+        if let Some(dir) = Self::mounted_dialog_dir(choice.dir_cluster, choice.dir_path.as_str()) {
+            let path = crate::fs::vfs::join(dir.as_str(), choice.name.as_str());
+            let (text, status) = match crate::fs::vfs::read(path.as_str()) {
+                Ok(data) => match core::str::from_utf8(&data[..data.len().min(NOTEPAD_MAX_TEXT_BYTES)]) {
+                    Ok(text) => (String::from(text), alloc::format!("Opened {}", path)),
+                    Err(_) => (
+                        String::new(),
+                        String::from("File is not UTF-8 legible. Edit manually or open another file."),
+                    ),
+                },
+                Err(e) => (String::new(), alloc::format!("Open failed: {}", e)),
+            };
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                win.load_notepad_document(0, choice.dir_path.as_str(), choice.name.as_str(), text.as_str(), status.as_str());
+            }
+            return;
+        }
+        if let Some(index) = choice.device_index {
+            if !self.force_mount_volume_index(index) {
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    win.set_notepad_status("Open failed: no se pudo montar la unidad.");
+                }
+                return;
+            }
+        }
+        let item = ExplorerItem::new(choice.name.as_str(), ExplorerItemKind::File, choice.cluster, choice.size);
+        let (text, status) = self.read_notepad_text(&item);
+        if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+            win.load_notepad_document(
+                choice.dir_cluster,
+                choice.dir_path.as_str(),
+                choice.name.as_str(),
+                text.as_str(),
+                status.as_str(),
+            );
+        }
+    }
+
+    fn confirm_notepad_delete(&mut self, win_id: usize) {
+        let name = match self.windows.iter().find(|w| w.id == win_id) {
+            Some(win) => String::from(win.notepad_file_name.trim()),
+            None => return,
+        };
+        if name.is_empty() {
+            // Reports the empty name in the status bar.
+            self.delete_notepad_file(win_id);
+            return;
+        }
+        let text = alloc::format!("Se eliminara {} del disco. Esta accion no se puede deshacer.", name);
+        self.open_dialog(
+            Dialog::confirm("Eliminar archivo", text.as_str(), "Eliminar"),
+            DialogPurpose::NotepadDelete(win_id),
+        );
+    }
+
+    fn begin_print_dialog(&mut self, win_id: usize) {
+        let mut printers = crate::print::printer_names();
+        if printers.is_empty() {
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                if win.is_notepad() {
+                    win.set_notepad_status("Buscando impresoras...");
+                } else {
+                    win.browser_status = String::from("Buscando impresoras...");
+                    win.render();
+                }
+            }
+            {
+                let mut pump = || self.pump_ui_while_blocked_net();
+                crate::print::discover(&mut pump);
+            }
+            printers = crate::print::printer_names();
+        }
+        if printers.is_empty() {
+            self.show_notice(
+                "Imprimir",
+                "No se encontraron impresoras en la red. Agrega una con 'print add ipp://<host>/ipp/print'.",
+            );
+            return;
+        }
+        let selected = crate::print::default_index(&printers);
+        self.open_dialog(Dialog::print("Imprimir", printers, selected), DialogPurpose::Print(win_id));
+    }
+
+    /// The notepad text, or the rendered browser page (its text when there is
+    /// no page surface), sent to the printer picked in the print dialog.
+    fn print_window(&mut self, win_id: usize, printer: usize, copies: u32) {
+        let Some(printer) = crate::print::printer_names().get(printer).cloned() else {
+            return;
+        };
+        let document = match self.windows.iter().find(|w| w.id == win_id) {
+            Some(win) if win.is_notepad() => crate::print::Document::Text {
+                title: String::from(match win.notepad_file_name.trim() {
+                    "" => "Sin titulo",
+                    name => name,
+                }),
+                text: String::from(win.notepad_area.text()),
+            },
+            Some(win) if win.is_browser() && !win.browser_surface_pixels.is_empty() => {
+                crate::print::Document::Image {
+                    title: win.browser_url.clone(),
+                    width: win.browser_surface_width,
+                    height: win.browser_surface_height,
+                    pixels: win.browser_surface_pixels.clone(),
+                }
+            }
+            Some(win) if win.is_browser() => crate::print::Document::Text {
+                title: win.browser_url.clone(),
+                text: win.browser_content_lines.join("\n"),
+            },
+            _ => return,
+        };
+        let result = {
+            let mut pump = || self.pump_ui_while_blocked_net();
+            crate::print::print_document(printer.as_str(), &document, copies, &mut pump)
+        };
+        let status = match &result {
+            Ok(id) => alloc::format!("Trabajo {} enviado a {}", id, printer),
+            Err(e) => alloc::format!("No se pudo imprimir: {}", e),
+        };
+        let urgency = if result.is_ok() {
+            crate::gui::notifications::Urgency::Success
+        } else {
+            crate::gui::notifications::Urgency::Error
+        };
+        crate::gui::notifications::post("print", "Impresion", status.as_str(), urgency);
+        if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+            if win.is_notepad() {
+                win.set_notepad_status(status.as_str());
+            } else {
+                win.browser_status = status;
+                win.render();
+            }
+        }
+    }
+
+    fn begin_browser_download(&mut self, win_id: usize) {
+        let url = match self.windows.iter().find(|w| w.id == win_id) {
+            Some(win) => String::from(win.browser_url.trim()),
+            None => return,
+        };
+        if !Self::is_http_url(url.as_str()) {
+            self.show_notice("Descargar", "Solo se pueden descargar direcciones http:// o https://.");
+            return;
+        }
+        let name = Self::derive_filename_from_url(url.as_str());
+        self.open_file_dialog(
+            "Guardar descarga",
+            FileDialogMode::Save,
+            "Downloads",
+            name.as_str(),
+            None,
+            DialogPurpose::BrowserDownload { win_id, url },
+        );
+    }
+
+    /// Downloads run as a terminal `fetch` into the chosen folder, the same
+    /// way FTP downloads from the explorer do.
+    fn start_browser_download(&mut self, win_id: usize, url: String, choice: FileChoice) {
+        if Self::mounted_dialog_dir(choice.dir_cluster, choice.dir_path.as_str()).is_some() {
+            self.show_notice(
+                "Descargar",
+                "Las descargas se guardan en el volumen de trabajo. Elige una carpeta fuera de los montajes y copiala despues con 'cp'.",
+            );
+            return;
+        }
+        if let Some(index) = choice.device_index {
+            if let Err(err) = self.force_mount_volume_index_for_write(index) {
+                self.show_notice("Descargar", alloc::format!("No se pudo descargar: {}", err).as_str());
+                return;
+            }
+        }
+        let name = Self::normalize_to_short_filename(choice.name.as_str(), "DOWNLOAD", "TXT");
+        let term_id = self
+            .windows
+            .iter()
+            .find(|w| w.is_terminal())
+            .map(|w| w.id)
+            .unwrap_or_else(|| self.create_window("Terminal Shell", 100, 100, 800, 500));
+        if let Some(term) = self.windows.iter_mut().find(|w| w.id == term_id) {
+            let mut path_text = choice.dir_path.clone();
+            if !path_text.ends_with('/') {
+                path_text.push('/');
+            }
+            term.current_dir_cluster = choice.dir_cluster;
+            term.current_path = path_text;
+        }
+        self.enqueue_terminal_command(term_id, alloc::format!("fetch {} {}", url, name).as_str(), None);
+        if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+            win.browser_status = alloc::format!("Descargando en {}{}", choice.dir_path, name);
+            win.render();
+        }
+    }
+
+    fn begin_screenshot(&mut self) -> String {
+        let Some(bmp) = crate::gui::screenshot::capture_bmp() else {
+            return String::from("Captura: framebuffer no disponible.");
+        };
+        let name = crate::gui::screenshot::next_name();
+        let size = bmp.len();
+        self.open_file_dialog(
+            "Guardar captura",
+            FileDialogMode::Save,
+            "Images",
+            name.as_str(),
+            Some("BMP"),
+            DialogPurpose::SaveScreenshot(bmp),
+        );
+        alloc::format!("Captura lista ({} bytes): elige donde guardarla.", size)
+    }
+
+    fn write_screenshot(&mut self, bmp: Vec<u8>, choice: FileChoice) {
+        if let Some(dir) = Self::mounted_dialog_dir(choice.dir_cluster, choice.dir_path.as_str()) {
+            let path = crate::fs::vfs::join(dir.as_str(), choice.name.as_str());
+            match crate::fs::vfs::write(path.as_str(), bmp.as_slice()) {
+                Ok(()) => crate::gui::notifications::post(
+                    "screenshot",
+                    "Captura guardada",
+                    alloc::format!("{} ({} bytes)", path, bmp.len()).as_str(),
+                    crate::gui::notifications::Urgency::Success,
+                ),
+                Err(err) => self.show_notice("Guardar captura", alloc::format!("No se pudo guardar: {}", err).as_str()),
+            }
+            return;
+        }
+        let ready = match choice.device_index {
+            Some(index) => self.force_mount_volume_index_for_write(index),
+            None if self.ensure_fat_ready() => Ok(()),
+            None => Err(String::from("FAT32/exFAT no disponible.")),
+        };
+        let result = ready.and_then(|_| {
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+            fat.write_text_file_in_dir(choice.dir_cluster, choice.name.as_str(), bmp.as_slice())
+                .map_err(String::from)
+        });
+        match result {
+            Ok(()) => {
+                let status = alloc::format!("Captura guardada: {}", choice.name);
+                self.refresh_explorer_windows_for_cluster_on_device(
+                    choice.dir_cluster,
+                    status.as_str(),
+                    None,
+                    choice.device_index,
+                );
+                crate::gui::notifications::post(
+                    "screenshot",
+                    "Captura guardada",
+                    alloc::format!("{}{} ({} bytes)", choice.dir_path, choice.name, bmp.len()).as_str(),
+                    crate::gui::notifications::Urgency::Success,
+                );
+            }
+            Err(err) => self.show_notice("Guardar captura", alloc::format!("No se pudo guardar: {}", err).as_str()),
         }
     }
 
@@ -25502,8 +27928,24 @@ impl Compositor {
                 self.last_mouse_right_down = m.right_down;
                 let is_new_left_click = m.left_down && !was_left_down;
                 let is_new_right_click = m.right_down && !was_right_down;
+                if is_new_left_click {
+                    self.clicks.press(
+                        self.mouse_pos,
+                        self.event_time_us,
+                        crate::gui::interaction::double_click_interval_us(),
+                    );
+                }
                 if !m.left_down {
                     self.ide_selection_drag = None;
+                    if let Some(id) = self.notepad_selection_drag.take() {
+                        if let Some(win) = self.windows.iter_mut().find(|w| w.id == id) {
+                            win.notepad_release_drag();
+                        }
+                    }
+                }
+
+                if crate::gui::osk::pointer(m.x, m.y, m.left_down, crate::timer::now_ms()) {
+                    return;
                 }
 
                 if self.copy_progress_prompt.is_some() {
@@ -25517,6 +27959,18 @@ impl Compositor {
                     }
                 }
 
+                if self.quick_search.is_some() {
+                    if is_new_left_click {
+                        self.handle_quick_search_click(m.x, m.y);
+                    }
+                    return;
+                }
+
+                if self.modal_dialog.is_some() {
+                    self.handle_modal_dialog_mouse(m.x, m.y, m.wheel_delta, is_new_left_click);
+                    return;
+                }
+
                 if self.ide_unsaved_prompt.is_some() {
                     if is_new_left_click {
                         self.handle_ide_unsaved_prompt_click(m.x, m.y);
@@ -25542,6 +27996,25 @@ impl Compositor {
                     return;
                 }
 
+                if self.notify_panel_open {
+                    if is_new_right_click {
+                        self.notify_panel_open = false;
+                        return;
+                    }
+                    if m.wheel_delta != 0 && self.notify_panel_rect().contains(self.mouse_pos) {
+                        self.scroll_notify_panel(m.wheel_delta);
+                        return;
+                    }
+                    if is_new_left_click && self.handle_notify_panel_click(m.x, m.y) {
+                        return;
+                    }
+                    return;
+                }
+
+                if is_new_left_click && self.handle_notify_toast_click(m.x, m.y) {
+                    return;
+                }
+
                 if self.desktop_switcher_open {
                     if is_new_right_click {
                         self.desktop_switcher_open = false;
@@ -25636,6 +28109,10 @@ impl Compositor {
                     return;
                 }
 
+                if self.update_drag_session(m.x, m.y, m.left_down) {
+                    return;
+                }
+
                 if self.handle_notepad_save_prompt_wheel(m.x, m.y, m.wheel_delta) {
                     return;
                 }
@@ -25649,6 +28126,15 @@ impl Compositor {
                 if self.handle_task_manager_wheel(m.x, m.y, m.wheel_delta) {
                     return;
                 }
+                if self.handle_about_pc_wheel(m.x, m.y, m.wheel_delta) {
+                    return;
+                }
+                if self.handle_mail_wheel(m.x, m.y, m.wheel_delta) {
+                    return;
+                }
+                if self.handle_notepad_wheel(m.x, m.y, m.wheel_delta) {
+                    return;
+                }
                 if self.handle_desktop_wheel(m.x, m.y, m.wheel_delta) {
                     return;
                 }
@@ -25661,6 +28147,9 @@ impl Compositor {
                 if m.left_down && self.handle_ide_selection_drag_update(m.x, m.y) {
                     return;
                 }
+                if m.left_down && self.handle_notepad_selection_drag_update(m.x, m.y) {
+                    return;
+                }
 
                 if !is_new_left_click {
                     return;
@@ -25676,6 +28165,10 @@ impl Compositor {
                     return;
                 }
 
+                if self.handle_window_context_menu_left_click(m.x, m.y) {
+                    return;
+                }
+
                 if self.handle_ide_context_menu_left_click() {
                     return;
                 }
@@ -25733,6 +28226,13 @@ impl Compositor {
 
                     let indicator_rect = self.taskbar_desktop_indicator_rect();
                     if indicator_rect.contains(Point { x: rel_x, y: rel_y }) {
+                        // Another workspace's cell switches; the active one opens the switcher.
+                        if let Some(idx) = self.taskbar_workspace_hit_test(Point { x: rel_x, y: rel_y }) {
+                            if idx != self.active_desktop_index {
+                                let _ = self.switch_to_desktop(idx);
+                                return;
+                            }
+                        }
                         self.desktop_switcher_open = !self.desktop_switcher_open;
                         if self.desktop_switcher_open {
                             self.minimized_overflow_open = false;
@@ -25808,13 +28308,21 @@ impl Compositor {
                             let settings_start = icons_end + PINNED_ARROW_W + 4;
                             if tray_rel >= settings_start && tray_rel < settings_start + SETTINGS_ICON_W {
                                 self.clock_panel_open = false;
+                                self.notify_panel_open = false;
                                 self.open_settings_window();
                                 return;
                             }
 
-                            let clock_start = settings_start + SETTINGS_ICON_W + 4;
+                            let notify_start = settings_start + SETTINGS_ICON_W + 4;
+                            if tray_rel >= notify_start && tray_rel < notify_start + NOTIFY_ICON_W {
+                                self.toggle_notify_panel();
+                                return;
+                            }
+
+                            let clock_start = notify_start + NOTIFY_ICON_W + 4;
                             if tray_rel >= clock_start && tray_rel < clock_start + CLOCK_W {
                                 self.clock_panel_open = !self.clock_panel_open;
+                                self.notify_panel_open = false;
                                 self.taskbar.start_menu_open = false;
                                 self.start_tools_open = false;
                                 self.start_games_open = false;
@@ -25894,6 +28402,14 @@ impl Compositor {
                                 self.start_games_open = false;
                                 self.start_apps_open = false;
                             }
+                            let about_item = self.tools_menu_item_rect(5);
+                            if about_item.contains(self.mouse_pos) {
+                                self.open_about_pc_window();
+                            }
+                            let mail_item = self.tools_menu_item_rect(6);
+                            if mail_item.contains(self.mouse_pos) {
+                                self.open_mail_window();
+                            }
                             return;
                         }
                     }
@@ -25979,7 +28495,7 @@ impl Compositor {
                             self.start_games_open = false;
                             self.start_apps_open = false;
                         } else if settings_item.contains(self.mouse_pos) {
-                            self.create_settings_window("Configuracion", 200, 100, 500, 400);
+                            self.create_settings_window("Configuracion", 200, 100, 500, 470);
                             self.taskbar.start_menu_open = false;
                             self.start_tools_open = false;
                             self.start_games_open = false;
@@ -26000,8 +28516,10 @@ impl Compositor {
                         } else if suspend_item.contains(self.mouse_pos) {
                             self.enter_suspend();
                         } else if shutdown_item.contains(self.mouse_pos) {
+                            let _ = self.save_session();
                             uefi::runtime::reset(ResetType::SHUTDOWN, Status::SUCCESS, None);
                         } else if restart_item.contains(self.mouse_pos) {
+                            let _ = self.save_session();
                             uefi::runtime::reset(ResetType::COLD, Status::SUCCESS, None);
                         }
                         return;
@@ -26053,6 +28571,15 @@ impl Compositor {
                             return;
                         }
 
+                        if self.clicks.count() == 2
+                            && self.windows[top_idx].title_bar_contains(self.mouse_pos.x, self.mouse_pos.y)
+                        {
+                            let (w, h) = (self.width, self.height);
+                            self.pointer_capture = None;
+                            self.windows[top_idx].maximize(w, h);
+                            return;
+                        }
+
                         if self.windows[top_idx].state == WindowState::Normal {
                             if self.windows[top_idx]
                                 .resize_grip_contains(self.mouse_pos.x, self.mouse_pos.y)
@@ -26082,6 +28609,16 @@ impl Compositor {
                         if self.handle_task_manager_click(win_id, self.mouse_pos.x, self.mouse_pos.y) {
                             return;
                         }
+                        if self.handle_boot_entries_click(win_id, self.mouse_pos.x, self.mouse_pos.y) {
+                            return;
+                        }
+                        if self.handle_pdf_viewer_click(win_id, self.mouse_pos.x, self.mouse_pos.y) {
+                            return;
+                        }
+                        if self.handle_applet_click(win_id, self.mouse_pos.x, self.mouse_pos.y) {
+                            return;
+                        }
+                        self.handle_mail_click(win_id, self.mouse_pos.x, self.mouse_pos.y);
                         return;
                     }
                 }
@@ -26094,10 +28631,17 @@ impl Compositor {
                     return;
                 }
             }
+            Event::Gamepad(g) => self.handle_gamepad_event(g),
             Event::Keyboard(k) => {
                 if self.handle_copy_progress_prompt_key(k.key, k.down) {
                     return;
                 }
+                if self.handle_quick_search_key(k.key, k.special, k.down) {
+                    return;
+                }
+                if self.handle_modal_dialog_key(k.key, k.special, k.down) {
+                    return;
+                }
                 if self.handle_ide_unsaved_prompt_key(k.key, k.down) {
                     return;
                 }
@@ -26118,6 +28662,13 @@ impl Compositor {
                     return;
                 }
 
+                if self.notify_panel_open {
+                    if k.down && matches!(k.key, Some('\x1b')) {
+                        self.notify_panel_open = false;
+                    }
+                    return;
+                }
+
                 if k.down && matches!(k.key, Some('\t')) {
                     if self.virtual_desktops.len() > 1 {
                         let next = (self.active_desktop_index + 1) % self.virtual_desktops.len();
@@ -26156,6 +28707,7 @@ impl Compositor {
                         cand_search,
                         cand_explorer,
                         cand_doom,
+                        cand_mail,
                         cand_visible,
                     ) =
                         match self.windows.iter().find(|w| w.id == candidate_id) {
@@ -26167,9 +28719,10 @@ impl Compositor {
                                 w.is_search(),
                                 w.is_explorer(),
                                 w.is_doom_launcher(),
+                                w.is_mail(),
                                 w.desktop_id == active_desktop,
                             ),
-                            None => (false, false, false, false, false, false, false, false),
+                            None => (false, false, false, false, false, false, false, false, false),
                         };
                     if !cand_visible {
                         effective_active_id = None;
@@ -26180,6 +28733,7 @@ impl Compositor {
                         && !cand_search
                         && !cand_explorer
                         && !cand_doom
+                        && !cand_mail
                     {
                         if let Some(run_win_id) = self.linux_runloop_active_win_id() {
                             if let Some(run_win) = self.windows.iter().find(|w| w.id == run_win_id) {
@@ -26214,6 +28768,36 @@ impl Compositor {
                         .find(|w| w.id == active_id)
                         .map(|w| w.is_doom_launcher())
                         .unwrap_or(false);
+                    let is_mail = self
+                        .windows
+                        .iter()
+                        .find(|w| w.id == active_id)
+                        .map(|w| w.is_mail())
+                        .unwrap_or(false);
+                    let is_task_manager = self
+                        .windows
+                        .iter()
+                        .find(|w| w.id == active_id)
+                        .map(|w| w.is_task_manager())
+                        .unwrap_or(false);
+                    let is_boot_entries = self
+                        .windows
+                        .iter()
+                        .find(|w| w.id == active_id)
+                        .map(|w| w.is_boot_entries())
+                        .unwrap_or(false);
+                    let is_pdf_viewer = self
+                        .windows
+                        .iter()
+                        .find(|w| w.id == active_id)
+                        .map(|w| w.is_pdf_viewer())
+                        .unwrap_or(false);
+                    let is_applet = self
+                        .windows
+                        .iter()
+                        .find(|w| w.id == active_id)
+                        .map(|w| w.is_applet())
+                        .unwrap_or(false);
 
                     if !is_terminal
                         && !is_notepad
@@ -26222,10 +28806,64 @@ impl Compositor {
                         && !is_search
                         && !is_explorer
                         && !is_doom
+                        && !is_mail
+                        && !is_task_manager
+                        && !is_boot_entries
+                        && !is_pdf_viewer
+                        && !is_applet
                     {
                         return;
                     }
 
+                    if is_applet {
+                        if let Some(win) = self.windows.iter_mut().find(|w| w.id == active_id) {
+                            let _ = win.applet_key(k.special, k.key);
+                        }
+                        return;
+                    }
+
+                    if is_pdf_viewer {
+                        if let Some(win) = self.windows.iter_mut().find(|w| w.id == active_id) {
+                            let _ = match (k.special, k.key) {
+                                (Some(SpecialKey::Left), _) => win.pdf_viewer_apply(PdfViewerClickAction::Prev),
+                                (Some(SpecialKey::Right), _) => win.pdf_viewer_apply(PdfViewerClickAction::Next),
+                                (Some(SpecialKey::Up), _) => win.pdf_viewer_scroll_by(-1),
+                                (Some(SpecialKey::Down), _) => win.pdf_viewer_scroll_by(1),
+                                (None, Some('+')) => win.pdf_viewer_apply(PdfViewerClickAction::ZoomIn),
+                                (None, Some('-')) => win.pdf_viewer_apply(PdfViewerClickAction::ZoomOut),
+                                (None, Some(' ')) => win.pdf_viewer_scroll_by(1),
+                                _ => false,
+                            };
+                        }
+                        return;
+                    }
+
+                    if is_boot_entries {
+                        if let Some(special) = k.special {
+                            if let Some(win) = self.windows.iter_mut().find(|w| w.id == active_id) {
+                                let _ = match special {
+                                    SpecialKey::Up => win.boot_entries_move_selection(-1),
+                                    SpecialKey::Down => win.boot_entries_move_selection(1),
+                                    SpecialKey::Left | SpecialKey::Right => false,
+                                };
+                            }
+                        }
+                        return;
+                    }
+
+                    if is_task_manager {
+                        if let Some(special) = k.special {
+                            if let Some(win) = self.windows.iter_mut().find(|w| w.id == active_id) {
+                                let _ = match special {
+                                    SpecialKey::Up => win.task_manager_move_selection(-1),
+                                    SpecialKey::Down => win.task_manager_move_selection(1),
+                                    SpecialKey::Left | SpecialKey::Right => false,
+                                };
+                            }
+                        }
+                        return;
+                    }
+
                     if is_doom {
                         if let Some(win) = self.windows.iter_mut().find(|w| w.id == active_id) {
                             let _ = win.doom_native_handle_input(k.key, k.special);
@@ -26233,6 +28871,19 @@ impl Compositor {
                         return;
                     }
 
+                    if is_mail {
+                        if let Some(special) = k.special {
+                            if let Some(win) = self.windows.iter_mut().find(|w| w.id == active_id) {
+                                let _ = match special {
+                                    SpecialKey::Up => win.mail_move_focus(-1),
+                                    SpecialKey::Down => win.mail_move_focus(1),
+                                    SpecialKey::Left | SpecialKey::Right => false,
+                                };
+                            }
+                            return;
+                        }
+                    }
+
                     if is_ide {
                         if let Some(special) = k.special {
                             if let Some(win) = self.windows.iter_mut().find(|w| w.id == active_id) {
@@ -26309,6 +28960,15 @@ impl Compositor {
                         }
                     }
 
+                    if is_notepad {
+                        if let Some(special) = k.special {
+                            if let Some(win) = self.windows.iter_mut().find(|w| w.id == active_id) {
+                                let _ = win.notepad_move_cursor(special);
+                            }
+                            return;
+                        }
+                    }
+
                     if is_terminal {
                         if let Some(special) = k.special {
                             let delta_rows = match special {
@@ -26333,7 +28993,10 @@ impl Compositor {
                             }
                             if is_terminal {
                                 if let Some(cmd) = cmd_to_run {
-                                    self.execute_command(active_id, &cmd);
+                                    // A program reading this terminal gets the line instead of the shell.
+                                    if !crate::procenv::push_stdin_line(active_id, cmd.as_str()) {
+                                        self.execute_command(active_id, &cmd);
+                                    }
                                 }
                             } else if is_browser {
                                 if let Some(url) = cmd_to_run {
@@ -26370,26 +29033,42 @@ impl Compositor {
         }
         self.needs_repaint = false;
         self.terminal_stream_mark_frame();
+        let paint_start = crate::perf::now_us();
+        crate::trace::begin("gui", "paint");
 
-        framebuffer::clear(0x021F3F);
+        framebuffer::clear(self.desktop_background);
         self.refresh_desktop_disk_icons(false);
         self.draw_desktop_disk_icons();
         self.draw_desktop_surface_overlay();
 
+        let effects = crate::gui::effects::enabled();
+        let now_us = crate::perf::now_us();
+        if !effects {
+            self.animations.clear();
+        }
         for win in &self.windows {
             if !self.window_on_active_desktop(win) {
                 continue;
             }
+            if let Some(animation) = self.animations.iter().find(|a| a.win_id == win.id) {
+                Self::draw_animated_window(win, animation.frame(now_us));
+                continue;
+            }
             if win.state != WindowState::Normal && win.state != WindowState::Maximized {
                 continue;
             }
+            let alpha = if effects { crate::gui::effects::alpha(win.opacity) } else { u8::MAX };
+            if effects && win.state == WindowState::Normal {
+                crate::gui::effects::draw_shadow(win.rect);
+            }
 
-            framebuffer::rect(
+            framebuffer::blend_rect(
                 win.rect.x as usize,
                 win.rect.y as usize,
                 win.rect.width as usize,
                 WINDOW_TITLE_BAR_H as usize,
                 0x1A1A1A,
+                alpha,
             );
             framebuffer::draw_text_5x7(
                 (win.rect.x + 8) as usize,
@@ -26440,13 +29119,42 @@ impl Compositor {
                 0xFFFFFF,
             );
 
-            framebuffer::blit(
-                win.rect.x as usize,
-                (win.rect.y + WINDOW_TITLE_BAR_H) as usize,
-                win.rect.width as usize,
-                (win.rect.height as i32 - WINDOW_TITLE_BAR_H).max(0) as usize,
-                &win.buffer,
-            );
+            let body_h = (win.rect.height as i32 - WINDOW_TITLE_BAR_H).max(0) as usize;
+            if alpha == u8::MAX {
+                framebuffer::blit(
+                    win.rect.x as usize,
+                    (win.rect.y + WINDOW_TITLE_BAR_H) as usize,
+                    win.rect.width as usize,
+                    body_h,
+                    &win.buffer,
+                );
+            } else {
+                framebuffer::blit_scaled_blend(
+                    win.rect.x,
+                    win.rect.y + WINDOW_TITLE_BAR_H,
+                    win.rect.width as usize,
+                    body_h,
+                    Self::window_body(win),
+                    win.rect.width as usize,
+                    alpha,
+                );
+            }
+            if let Some(draw) = win.browser_surface_draw_rect() {
+                // Above 1x the page canvas has more pixels than its logical rect.
+                let x = win.rect.x + draw.x;
+                let y = win.rect.y + WINDOW_TITLE_BAR_H + draw.y;
+                if x >= 0 && y >= 0 {
+                    framebuffer::blit_native(
+                        x as usize,
+                        y as usize,
+                        draw.width as usize,
+                        draw.height as usize,
+                        win.browser_surface_width as usize,
+                        win.browser_surface_height as usize,
+                        &win.browser_surface_pixels,
+                    );
+                }
+            }
 
             self.draw_explorer_selection_overlay_for_window(win);
 
@@ -26498,7 +29206,7 @@ impl Compositor {
             framebuffer::draw_text_5x7(
                 (search_item.x + 8).max(0) as usize,
                 (search_item.y + 8).max(0) as usize,
-                "Search",
+                crate::i18n::tr("menu.search").as_str(),
                 0xE9F3FF,
             );
 
@@ -26513,7 +29221,7 @@ impl Compositor {
             framebuffer::draw_text_5x7(
                 (favorites_item.x + 8).max(0) as usize,
                 (favorites_item.y + 8).max(0) as usize,
-                "Favoritos",
+                crate::i18n::tr("menu.favorites").as_str(),
                 0xFFFFFF,
             );
 
@@ -26528,7 +29236,7 @@ impl Compositor {
             framebuffer::draw_text_5x7(
                 (explorer_item.x + 8).max(0) as usize,
                 (explorer_item.y + 8).max(0) as usize,
-                "File Explorer",
+                crate::i18n::tr("menu.explorer").as_str(),
                 0xE3F4FF,
             );
 
@@ -26543,7 +29251,7 @@ impl Compositor {
             framebuffer::draw_text_5x7(
                 (browser_item.x + 8).max(0) as usize,
                 (browser_item.y + 8).max(0) as usize,
-                "Web Browser",
+                crate::i18n::tr("menu.browser").as_str(),
                 0xD0E0F0,
             );
 
@@ -26558,7 +29266,7 @@ impl Compositor {
             framebuffer::draw_text_5x7(
                 (settings_item.x + 8).max(0) as usize,
                 (settings_item.y + 8).max(0) as usize,
-                "Configuracion",
+                crate::i18n::tr("menu.settings").as_str(),
                 0xFFFFFF,
             );
 
@@ -26573,7 +29281,7 @@ impl Compositor {
             framebuffer::draw_text_5x7(
                 (tools_item.x + 8).max(0) as usize,
                 (tools_item.y + 8).max(0) as usize,
-                "Herramientas >",
+                crate::i18n::tr("menu.tools").as_str(),
                 0xDDEEFF,
             );
 
@@ -26588,7 +29296,7 @@ impl Compositor {
             framebuffer::draw_text_5x7(
                 (games_item.x + 8).max(0) as usize,
                 (games_item.y + 8).max(0) as usize,
-                "Juegos >",
+                crate::i18n::tr("menu.games").as_str(),
                 0xDEFFEF,
             );
 
@@ -26603,7 +29311,7 @@ impl Compositor {
             framebuffer::draw_text_5x7(
                 (apps_item.x + 8).max(0) as usize,
                 (apps_item.y + 8).max(0) as usize,
-                "Apps >",
+                crate::i18n::tr("menu.apps").as_str(),
                 0xEFEAFF,
             );
 
@@ -26618,7 +29326,7 @@ impl Compositor {
             framebuffer::draw_text_5x7(
                 (suspend_item.x + 8).max(0) as usize,
                 (suspend_item.y + 8).max(0) as usize,
-                "Suspender",
+                crate::i18n::tr("menu.suspend").as_str(),
                 0xCCCCFF,
             );
 
@@ -26633,7 +29341,7 @@ impl Compositor {
             framebuffer::draw_text_5x7(
                 (shutdown_item.x + 8).max(0) as usize,
                 (shutdown_item.y + 8).max(0) as usize,
-                "Apagar",
+                crate::i18n::tr("menu.shutdown").as_str(),
                 0xFFDDDD,
             );
 
@@ -26648,7 +29356,7 @@ impl Compositor {
             framebuffer::draw_text_5x7(
                 (restart_item.x + 8).max(0) as usize,
                 (restart_item.y + 8).max(0) as usize,
-                "Reiniciar",
+                crate::i18n::tr("menu.restart").as_str(),
                 0xFFEDDD,
             );
 
@@ -26748,6 +29456,36 @@ impl Compositor {
                     "Reproductor Video",
                     0xEAF4FF,
                 );
+
+                let about_item = self.tools_menu_item_rect(5);
+                framebuffer::rect(
+                    about_item.x.max(0) as usize,
+                    about_item.y.max(0) as usize,
+                    about_item.width as usize,
+                    about_item.height as usize,
+                    0x1F2A36,
+                );
+                framebuffer::draw_text_5x7(
+                    (about_item.x + 8).max(0) as usize,
+                    (about_item.y + 8).max(0) as usize,
+                    "Acerca de este PC",
+                    0xEAF4FF,
+                );
+
+                let mail_item = self.tools_menu_item_rect(6);
+                framebuffer::rect(
+                    mail_item.x.max(0) as usize,
+                    mail_item.y.max(0) as usize,
+                    mail_item.width as usize,
+                    mail_item.height as usize,
+                    0x2B3A4B,
+                );
+                framebuffer::draw_text_5x7(
+                    (mail_item.x + 8).max(0) as usize,
+                    (mail_item.y + 8).max(0) as usize,
+                    "Correo",
+                    0xEAF4FF,
+                );
             }
 
             if self.start_games_open {
@@ -26854,23 +29592,72 @@ impl Compositor {
         self.draw_ide_context_menu_overlay();
         self.draw_pinned_context_menu_overlay();
         self.draw_clock_panel_overlay();
+        self.draw_notify_panel_overlay();
+        self.draw_notify_toasts_overlay();
         self.draw_desktop_create_folder_prompt();
         self.draw_rename_prompt();
         self.draw_ide_unsaved_prompt();
         self.draw_notepad_save_prompt();
+        self.draw_modal_dialog_overlay();
+        self.draw_quick_search_overlay();
         self.draw_copy_progress_prompt();
         self.draw_desktop_switcher_overlay();
         self.draw_minimized_overflow_overlay();
+        self.draw_window_context_menu_overlay();
+        self.draw_drag_overlay();
+        crate::gui::osk::draw(self.width, self.taskbar.rect.y.max(0) as usize);
+        self.draw_perf_hud_overlay();
         self.draw_cursor();
+        self.animations.retain(|animation| !animation.finished(now_us));
+        if !self.animations.is_empty() {
+            self.needs_repaint = true;
+        }
+        crate::trace::end("gui", "paint");
+        crate::perf::record(crate::perf::Phase::Paint, paint_start);
+        // Input that arrived while drawing waits in the queue for the next frame.
+        let input_start = crate::perf::now_us();
+        crate::gui::event_queue::pump();
+        crate::perf::record(crate::perf::Phase::Input, input_start);
+        let present_start = crate::perf::now_us();
+        crate::trace::begin("gui", "present");
         framebuffer::present();
+        crate::trace::end("gui", "present");
+        crate::perf::record(crate::perf::Phase::Present, present_start);
         // Background services run after presenting a frame to avoid starving UI refresh.
+        let layout_start = crate::perf::now_us();
+        crate::trace::begin("gui", "services");
         self.service_background_tasks();
+        crate::trace::end("gui", "services");
+        crate::perf::record(crate::perf::Phase::Layout, layout_start);
+    }
+
+    fn draw_perf_hud_overlay(&mut self) {
+        if !crate::perf::hud_visible() {
+            return;
+        }
+        let lines = crate::perf::hud_lines();
+        let width = lines.iter().map(|line| line.len()).max().unwrap_or(0) * 6 + 16;
+        let height = lines.len() * 12 + 10;
+        let (screen_w, _) = framebuffer::dimensions();
+        let x = screen_w.saturating_sub(width + 8);
+        let y = 8;
+        framebuffer::rect(x, y, width, height, 0x0B1220);
+        framebuffer::rect(x, y, width, 1, 0x38BDF8);
+        for (idx, line) in lines.iter().enumerate() {
+            let color = if idx == 0 { 0x7DD3FC } else { 0xD1D5DB };
+            framebuffer::draw_text_5x7(x + 8, y + 6 + idx * 12, line.as_str(), color);
+        }
+    }
+
+    pub fn toggle_perf_hud(&mut self) {
+        crate::perf::toggle_hud();
+        self.needs_repaint = true;
     }
 
     fn draw_taskbar_overlay(&mut self) {
         use crate::gui::widgets::taskbar::*;
 
-        let bg_color = 0x111111;
+        let bg_color = self.taskbar.background;
         framebuffer::rect(
             self.taskbar.rect.x as usize,
             self.taskbar.rect.y as usize,
@@ -26939,17 +29726,29 @@ impl Compositor {
         let indicator = self.taskbar_desktop_indicator_rect();
         self.taskbar_window.fill_rect(indicator, Color(0x2B2B2B));
         self.taskbar_window.draw_border(indicator, Color(0x555555));
-        let desktop_text = alloc::format!(
-            "{}:{}",
-            self.active_desktop_index + 1,
-            self.virtual_desktops.len()
-        );
-        self.taskbar_window.draw_text(
-            (indicator.x + 6) as u32,
-            (indicator.y + 14) as u32,
-            desktop_text.as_bytes(),
-            Color(0xE6E6E6),
-        );
+        for idx in 0..self.virtual_desktops.len() {
+            let cell = self.taskbar_workspace_cell_rect(idx);
+            let active = idx == self.active_desktop_index;
+            if active {
+                self.taskbar_window.fill_rect(cell, Color(0x1D4ED8));
+            }
+            let number = alloc::format!("{}", (idx + 1) % 10);
+            self.taskbar_window.draw_text(
+                (cell.x + 5) as u32,
+                (cell.y + 12) as u32,
+                number.as_bytes(),
+                Color(if active { 0xFFFFFF } else { 0xB8B8B8 }),
+            );
+            // One dot per window, up to three.
+            let windows = self.desktop_window_count(idx).min(3) as i32;
+            let dots_x = cell.x + (cell.width as i32 - (windows * 4 - 1)) / 2;
+            for dot in 0..windows {
+                self.taskbar_window.fill_rect(
+                    Rect::new(dots_x + dot * 4, cell.y + cell.height as i32 - 4, 3, 2),
+                    Color(0x93C5FD),
+                );
+            }
+        }
 
         // ── Right-side tray area ──
         let tray_x = self.taskbar.tray_start_x();
@@ -27058,6 +29857,46 @@ impl Compositor {
             cx += SETTINGS_ICON_W + 4;
         }
 
+        // Notification bell + unread badge
+        {
+            let unread = crate::gui::notifications::unread_count();
+            let bell_rect = Rect::new(cx, icon_y + 4, NOTIFY_ICON_W as u32, 26);
+            self.taskbar_window.fill_rect(
+                bell_rect,
+                if self.notify_panel_open { Color(0x24384D) } else { Color(0x333333) },
+            );
+            self.taskbar_window.draw_border(
+                bell_rect,
+                if self.notify_panel_open { Color(0x6FA8DC) } else { Color(0x555555) },
+            );
+            let bell_color = if unread > 0 { Color(0xF5D76E) } else { Color(0x999999) };
+            let bx = cx + 9;
+            let by = icon_y + 9;
+            self.taskbar_window.fill_rect(Rect::new(bx + 4, by, 2, 2), bell_color);
+            self.taskbar_window.fill_rect(Rect::new(bx + 2, by + 2, 6, 7), bell_color);
+            self.taskbar_window.fill_rect(Rect::new(bx, by + 9, 10, 2), bell_color);
+            self.taskbar_window.fill_rect(Rect::new(bx + 4, by + 12, 2, 2), bell_color);
+            if unread > 0 {
+                let badge = if unread > 9 {
+                    String::from("9+")
+                } else {
+                    alloc::format!("{}", unread)
+                };
+                let badge_w = 6 * badge.len() as u32 + 4;
+                self.taskbar_window.fill_rect(
+                    Rect::new(cx + NOTIFY_ICON_W - badge_w as i32, icon_y + 2, badge_w, 11),
+                    Color(0xC0392B),
+                );
+                self.taskbar_window.draw_text(
+                    (cx + NOTIFY_ICON_W - badge_w as i32 + 2) as u32,
+                    (icon_y + 4) as u32,
+                    badge.as_bytes(),
+                    Color(0xFFFFFF),
+                );
+            }
+            cx += NOTIFY_ICON_W + 4;
+        }
+
         // Clock HH:MM + DD/MM
         {
             let clock_bg = if self.clock_panel_open {
@@ -27248,6 +30087,15 @@ impl Compositor {
                 label,
                 text,
             );
+            if exists {
+                let windows = alloc::format!("{} ventanas", self.desktop_window_count(idx));
+                framebuffer::draw_text_5x7(
+                    (card.x + 8).max(0) as usize,
+                    (card.y + 44).max(0) as usize,
+                    windows.as_str(),
+                    text,
+                );
+            }
 
             if exists && self.virtual_desktops.len() > 1 {
                 let close = self.desktop_switcher_card_close_rect(idx);
@@ -27483,7 +30331,7 @@ impl Compositor {
         self.start_tools_open = false;
         self.start_games_open = false;
         self.start_apps_open = false;
-        self.create_settings_window("Configuracion", 200, 100, 500, 400);
+        self.create_settings_window("Configuracion", 200, 100, 500, 470);
     }
 
     fn open_task_manager_window(&mut self) {
@@ -27498,7 +30346,154 @@ impl Compositor {
         self.start_tools_open = false;
         self.start_games_open = false;
         self.start_apps_open = false;
-        self.create_task_manager_window("Task Manager", 220, 120, 560, 420);
+        self.create_task_manager_window("Task Manager", 200, 90, 660, 500);
+    }
+
+    fn open_about_pc_window(&mut self) {
+        if let Some(id) = self
+            .windows
+            .iter()
+            .find(|w| w.is_about_pc() && self.window_on_active_desktop(w))
+            .map(|w| w.id)
+        {
+            self.active_window_id = Some(id);
+            return;
+        }
+        self.taskbar.start_menu_open = false;
+        self.start_tools_open = false;
+        self.start_games_open = false;
+        self.start_apps_open = false;
+        self.create_about_pc_window("Acerca de este PC", 200, 90, 620, 480);
+    }
+
+    fn open_boot_entries_window(&mut self) {
+        if let Some(id) = self
+            .windows
+            .iter()
+            .find(|w| w.is_boot_entries() && self.window_on_active_desktop(w))
+            .map(|w| w.id)
+        {
+            self.active_window_id = Some(id);
+            return;
+        }
+        self.taskbar.start_menu_open = false;
+        self.start_tools_open = false;
+        self.start_games_open = false;
+        self.start_apps_open = false;
+        self.create_boot_entries_window("Entradas de arranque", 200, 90, 720, 520);
+    }
+
+    fn open_pdf_viewer_window(&mut self) -> usize {
+        if let Some(id) = self
+            .windows
+            .iter()
+            .find(|w| w.is_pdf_viewer() && w.pdf_viewer_document.is_none() && self.window_on_active_desktop(w))
+            .map(|w| w.id)
+        {
+            self.active_window_id = Some(id);
+            return id;
+        }
+        self.taskbar.start_menu_open = false;
+        self.start_tools_open = false;
+        self.start_games_open = false;
+        self.start_apps_open = false;
+        self.create_pdf_viewer_window("Visor PDF", 170, 60, 820, 680)
+    }
+
+    fn open_calculator_window(&mut self) {
+        if let Some(id) = self
+            .windows
+            .iter()
+            .find(|w| w.is_calculator() && self.window_on_active_desktop(w))
+            .map(|w| w.id)
+        {
+            self.active_window_id = Some(id);
+            return;
+        }
+        self.taskbar.start_menu_open = false;
+        self.start_tools_open = false;
+        self.start_games_open = false;
+        self.start_apps_open = false;
+        self.create_calculator_window("Calculadora", 260, 80, 320, 520);
+    }
+
+    fn open_calendar_window(&mut self) {
+        if let Some(id) = self
+            .windows
+            .iter()
+            .find(|w| w.is_calendar() && self.window_on_active_desktop(w))
+            .map(|w| w.id)
+        {
+            self.active_window_id = Some(id);
+            return;
+        }
+        self.taskbar.start_menu_open = false;
+        self.start_tools_open = false;
+        self.start_games_open = false;
+        self.start_apps_open = false;
+        self.create_calendar_window("Calendario", 220, 70, 420, 580);
+    }
+
+    fn open_clock_window(&mut self) {
+        if let Some(id) = self
+            .windows
+            .iter()
+            .find(|w| w.is_clock() && self.window_on_active_desktop(w))
+            .map(|w| w.id)
+        {
+            self.active_window_id = Some(id);
+            return;
+        }
+        self.taskbar.start_menu_open = false;
+        self.start_tools_open = false;
+        self.start_games_open = false;
+        self.start_apps_open = false;
+        self.create_clock_window("Reloj y alarmas", 240, 90, 440, 420);
+    }
+
+    fn open_recorder_window(&mut self) {
+        if let Some(id) = self
+            .windows
+            .iter()
+            .find(|w| w.is_recorder() && self.window_on_active_desktop(w))
+            .map(|w| w.id)
+        {
+            self.active_window_id = Some(id);
+            return;
+        }
+        self.taskbar.start_menu_open = false;
+        self.start_tools_open = false;
+        self.start_games_open = false;
+        self.start_apps_open = false;
+        self.create_recorder_window("Grabadora de voz", 260, 110, 380, 240);
+    }
+
+    /// Show a parsed document in a new PDF viewer window.
+    fn show_pdf_document(&mut self, name: &str, document: crate::pdf::Document, page: usize) -> usize {
+        let title = alloc::format!("Visor PDF - {}", Self::trim_ascii_line(name, 28));
+        let viewer_id = self.open_pdf_viewer_window();
+        if let Some(win) = self.windows.iter_mut().find(|w| w.id == viewer_id) {
+            win.title = title;
+            win.load_pdf_viewer(name, document, page);
+        }
+        viewer_id
+    }
+
+    fn open_mail_window(&mut self) {
+        if let Some(id) = self
+            .windows
+            .iter()
+            .find(|w| w.is_mail() && self.window_on_active_desktop(w))
+            .map(|w| w.id)
+        {
+            self.active_window_id = Some(id);
+            return;
+        }
+        self.taskbar.start_menu_open = false;
+        self.start_tools_open = false;
+        self.start_games_open = false;
+        self.start_apps_open = false;
+        self.create_mail_window("Correo", 160, 70, 860, 560);
     }
 
     fn open_video_player_window(&mut self) {
@@ -27577,6 +30572,9 @@ impl Compositor {
                         write_time: 0,
                     };
                     self.open_png_from_explorer_file(0, &temp_item);
+                } else if Self::is_pdf_file_name(item.label.as_str()) {
+                    let temp_item = ExplorerItem::new(item.label.as_str(), ExplorerItemKind::File, item.cluster, item.size);
+                    self.open_pdf_from_explorer_file(0, &temp_item);
                 } else if Self::is_audio_file_name(item.label.as_str()) {
                     // Audio file — open in media player
                     self.open_media_player_file(item.cluster, item.label.as_str(), item.size);
@@ -27594,7 +30592,7 @@ impl Compositor {
                         write_date: 0,
                         write_time: 0,
                     };
-                    let root = unsafe { crate::fat32::GLOBAL_FAT.root_cluster };
+                    let root = unsafe { crate::fat32::GLOBAL_FAT.get().root_cluster };
                     self.open_notepad_from_explorer_file(root, String::from("/"), &temp_item);
                 }
             }
@@ -27664,7 +30662,12 @@ impl Compositor {
     pub fn minimize_window(&mut self, id: usize) {
         let mut tab_to_add: Option<MinimizedWindowTab> = None;
         let mut was_active = false;
+        let mut shown: Option<(Rect, u8)> = None;
+        let active_desktop = self.active_desktop_id();
         if let Some(win) = self.windows.iter_mut().find(|w| w.id == id) {
+            if win.state != WindowState::Minimized && win.desktop_id == active_desktop {
+                shown = Some((win.rect, win.opacity));
+            }
             win.minimize();
             tab_to_add = Some(MinimizedWindowTab {
                 win_id: id,
@@ -27678,6 +30681,10 @@ impl Compositor {
             self.minimized_windows.push(tab);
             self.clamp_minimized_overflow_scroll();
         }
+        if let Some((rect, opacity)) = shown {
+            let tab = self.minimized_tab_rect_for(id);
+            self.start_window_animation(id, crate::gui::effects::Kind::Minimize, rect, tab, opacity);
+        }
         if was_active {
             self.sync_active_window_for_desktop();
         }
@@ -27685,18 +30692,120 @@ impl Compositor {
 
     pub fn restore_window(&mut self, id: usize) {
         let active_desktop = self.active_desktop_id();
+        let tab = self.minimized_tab_rect_for(id);
         let mut should_focus = false;
+        let mut shown: Option<(Rect, u8)> = None;
         if let Some(win) = self.windows.iter_mut().find(|w| w.id == id) {
+            let was_minimized = win.state == WindowState::Minimized;
             win.restore();
             should_focus = win.desktop_id == active_desktop;
+            if was_minimized && should_focus {
+                shown = Some((win.rect, win.opacity));
+            }
         }
         if should_focus {
             self.active_window_id = Some(id);
         }
+        if let Some((rect, opacity)) = shown {
+            self.start_window_animation(id, crate::gui::effects::Kind::Restore, rect, tab, opacity);
+        }
         self.minimized_windows.retain(|tab| tab.win_id != id);
         self.clamp_minimized_overflow_scroll();
     }
 
+    /// Taskbar button of minimized window `id`, or the overflow button when
+    /// it has no room of its own.
+    fn minimized_tab_rect_for(&self, id: usize) -> Rect {
+        let (tabs, visible_count, _) = self.minimized_tabs_state();
+        tabs.iter()
+            .position(|tab| tab.win_id == id)
+            .filter(|index| *index < visible_count)
+            .map(|index| self.minimized_tab_rect(index))
+            .or_else(|| self.minimized_overflow_button_rect())
+            .unwrap_or_else(|| self.minimized_tab_rect(0))
+    }
+
+    fn start_window_animation(
+        &mut self,
+        id: usize,
+        kind: crate::gui::effects::Kind,
+        rect: Rect,
+        tab: Rect,
+        opacity: u8,
+    ) {
+        if self.headless || !crate::gui::effects::enabled() {
+            return;
+        }
+        let alpha = crate::gui::effects::alpha(opacity);
+        let animation = crate::gui::effects::Animation::new(id, kind, rect, tab, alpha, crate::perf::now_us());
+        self.animations.retain(|running| running.win_id != id);
+        self.animations.push(animation);
+        self.needs_repaint = true;
+    }
+
+    /// `win` stretched into `frame`; the title bar is drawn without
+    /// its text and buttons.
+    fn draw_animated_window(win: &Window, frame: crate::gui::effects::Frame) {
+        let full_h = win.rect.height.max(1);
+        let title_h = (WINDOW_TITLE_BAR_H as u32 * frame.rect.height / full_h).min(frame.rect.height);
+        if frame.rect.x >= 0 && frame.rect.y >= 0 {
+            framebuffer::blend_rect(
+                frame.rect.x as usize,
+                frame.rect.y as usize,
+                frame.rect.width as usize,
+                title_h as usize,
+                0x1A1A1A,
+                frame.alpha,
+            );
+        }
+        framebuffer::blit_scaled_blend(
+            frame.rect.x,
+            frame.rect.y + title_h as i32,
+            frame.rect.width as usize,
+            (frame.rect.height - title_h) as usize,
+            Self::window_body(win),
+            win.rect.width as usize,
+            frame.alpha,
+        );
+    }
+
+    /// The rows of `win.buffer` below the title bar.
+    fn window_body(win: &Window) -> &[u32] {
+        let body_h = (win.rect.height as i32 - WINDOW_TITLE_BAR_H).max(0) as usize;
+        &win.buffer[..(win.rect.width as usize * body_h).min(win.buffer.len())]
+    }
+
+    /// `effects opacity <percent> [title]`: the window whose title contains
+    /// `title`, or `win_id` itself.
+    fn set_window_opacity_lines(&mut self, win_id: usize, args: &str) -> Vec<String> {
+        let Some((percent, title)) = crate::gui::effects::parse_opacity_args(args) else {
+            return alloc::vec![alloc::format!(
+                "Uso: effects opacity <{}-100> [titulo]",
+                crate::gui::effects::MIN_OPACITY
+            )];
+        };
+        let active_desktop = self.active_desktop_id();
+        let needle = title.to_ascii_lowercase();
+        let target = if needle.is_empty() {
+            self.windows.iter_mut().find(|w| w.id == win_id)
+        } else {
+            self.windows
+                .iter_mut()
+                .rev()
+                .find(|w| w.desktop_id == active_desktop && w.title.to_ascii_lowercase().contains(needle.as_str()))
+        };
+        let Some(win) = target else {
+            return alloc::vec![alloc::format!("effects: no hay ninguna ventana '{}'", title)];
+        };
+        win.opacity = percent;
+        let mut out = alloc::vec![alloc::format!("Opacidad de '{}': {}%", win.title, percent)];
+        if !crate::gui::effects::enabled() {
+            out.push(String::from("Los efectos estan desactivados; se vera opaca hasta 'effects on'."));
+        }
+        self.needs_repaint = true;
+        out
+    }
+
     fn request_window_close(&mut self, id: usize) {
         if self.ide_request_unsaved_guard(id, IdeUnsavedAction::Close) {
             return;
@@ -27712,6 +30821,7 @@ impl Compositor {
             win.close();
             self.closed_windows.push(win);
         }
+        self.animations.retain(|animation| animation.win_id != id);
         let recent_binding = self.take_window_recent_binding(id);
         if let (Some(binding), Some(desktop_id)) = (recent_binding, closed_desktop_id) {
             self.record_recent_shortcut_for_desktop(
@@ -27746,6 +30856,9 @@ impl Compositor {
         {
             self.ide_selection_drag = None;
         }
+        if self.notepad_selection_drag == Some(id) {
+            self.notepad_selection_drag = None;
+        }
         if self.linux_bridge_window_id == Some(id) {
             self.linux_bridge_window_id = None;
         }
@@ -28023,13 +31136,13 @@ impl Compositor {
         };
 
         let already_mounted = self.current_volume_device_index == Some(index)
-            && unsafe { crate::fat32::GLOBAL_FAT.bytes_per_sector != 0 };
+            && unsafe { crate::fat32::GLOBAL_FAT.get().bytes_per_sector != 0 };
         if already_mounted {
             return true;
         }
 
         let mounted = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             fat.mount_uefi_block_device(index).is_ok()
         };
         if mounted {
@@ -28127,7 +31240,7 @@ impl Compositor {
         let candidates = self.auto_mount_candidate_indices();
         for index in candidates {
             let mounted = {
-                let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 fat.mount_uefi_block_device(index).is_ok()
             };
             if mounted {
@@ -28141,7 +31254,7 @@ impl Compositor {
 
     fn ensure_volume_index_mounted(&mut self, index: usize) -> bool {
         let already_mounted = self.current_volume_device_index == Some(index)
-            && unsafe { crate::fat32::GLOBAL_FAT.bytes_per_sector != 0 };
+            && unsafe { crate::fat32::GLOBAL_FAT.get().bytes_per_sector != 0 };
         if already_mounted {
             return true;
         }
@@ -28151,7 +31264,7 @@ impl Compositor {
 
     fn force_mount_volume_index(&mut self, index: usize) -> bool {
         let mounted = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             fat.mount_uefi_block_device(index).is_ok()
         };
         if mounted {
@@ -28170,7 +31283,7 @@ impl Compositor {
 
     fn ensure_fat_ready(&mut self) -> bool {
         {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             if fat.bytes_per_sector != 0 {
                 return true;
             }
@@ -28185,7 +31298,7 @@ impl Compositor {
         }
 
         let init_ok = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             fat.init()
         };
         if init_ok {
@@ -28434,7 +31547,7 @@ impl Compositor {
         let listing_device_index = self.current_volume_device_index;
 
         let (cluster, path, items) = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             let mut effective_cluster = if cluster < 2 {
                 fat.root_cluster
             } else {
@@ -28585,7 +31698,7 @@ impl Compositor {
 
     fn open_explorer_volume(&mut self, win_id: usize, index: usize) {
         let (root_cluster, path, status) = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             match fat.mount_uefi_block_device(index) {
                 Ok(vol) => {
                     self.clear_manual_unmount_lock();
@@ -28680,7 +31793,7 @@ impl Compositor {
             .or(self.current_volume_device_index);
 
         let (root_cluster, volume_label, parent_cluster) = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             let root = fat.root_cluster;
             let volume = device_hint
                 .map(|index| Self::explorer_volume_label_for_mounted(index, fat))
@@ -29053,7 +32166,7 @@ impl Compositor {
 
         self.start_app_shortcuts.clear();
 
-        let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+        let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
         if fat.bytes_per_sector == 0 {
             if self.manual_unmount_lock || !fat.init() {
                 return;
@@ -29119,7 +32232,7 @@ impl Compositor {
         let mut preview_lines = Vec::new();
 
         {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
 
             if item.cluster < 2 || item.size == 0 {
                 preview_lines.push(String::from("(empty file)"));
@@ -29187,7 +32300,7 @@ impl Compositor {
         let mut file_bytes = Vec::new();
         file_bytes.resize(file_len, 0);
         let read_len = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             match fat.read_file_sized(item.cluster, file_len, &mut file_bytes) {
                 Ok(n) => n,
                 Err(_) => {
@@ -29249,11 +32362,64 @@ impl Compositor {
         }
     }
 
+    fn open_pdf_from_explorer_file(&mut self, explorer_win_id: usize, item: &ExplorerItem) {
+        if !self.ensure_fat_ready_for_explorer(explorer_win_id) {
+            return;
+        }
+
+        let error = if item.cluster < 2 || item.size == 0 {
+            Some(String::from("Archivo PDF vacio o cluster invalido."))
+        } else if item.size as usize > PDF_VIEWER_MAX_FILE_BYTES {
+            Some(alloc::format!("PDF demasiado grande (max {} bytes).", PDF_VIEWER_MAX_FILE_BYTES))
+        } else {
+            let mut file_bytes = alloc::vec![0u8; item.size as usize];
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+            match fat.read_file_sized(item.cluster, item.size as usize, &mut file_bytes) {
+                Ok(n) => {
+                    file_bytes.truncate(n);
+                    match crate::pdf::Document::parse(file_bytes) {
+                        Ok(document) => {
+                            let pages = document.page_count();
+                            let viewer_id = self.show_pdf_document(item.label.as_str(), document, 0);
+                            let recent_cmd = Self::recent_file_command(
+                                "pdf",
+                                self.current_volume_device_index,
+                                0,
+                                item.cluster,
+                                item.size,
+                                "/",
+                                item.label.as_str(),
+                            );
+                            self.set_window_recent_binding(viewer_id, item.label.as_str(), recent_cmd.as_str());
+                            if let Some(win) = self.windows.iter_mut().find(|w| w.id == explorer_win_id) {
+                                win.set_explorer_status(
+                                    alloc::format!("PDF abierto: {} ({} paginas)", item.label, pages).as_str(),
+                                );
+                            }
+                            None
+                        }
+                        Err(err) => Some(String::from(err)),
+                    }
+                }
+                Err(_) => Some(String::from("Error leyendo el PDF desde FAT32.")),
+            }
+        };
+
+        if let Some(err) = error {
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == explorer_win_id) {
+                win.set_explorer_preview(
+                    alloc::format!("No se pudo abrir {}", item.label).as_str(),
+                    alloc::vec![err],
+                );
+            }
+        }
+    }
+
     fn open_notepad_blank(&mut self) {
         let note_id = self.create_notepad_window("Notepad", 180, 90, 860, 560);
 
         let (dir_cluster, dir_path, status) = if self.ensure_fat_ready() {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             let label = Self::volume_label_text(fat).unwrap_or(String::from("USB"));
             (
                 fat.root_cluster,
@@ -29317,7 +32483,7 @@ impl Compositor {
 
         if is_rpv {
             if self.ensure_fat_ready() {
-                let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 let mut header_buf = [0u8; 16];
                 if let Ok(16) = fat.read_file_range(file_cluster, file_size as usize, 0, &mut header_buf) {
                     if &header_buf[0..4] == b"RPV1" {
@@ -29421,7 +32587,7 @@ impl Compositor {
         }
 
         let read_size = (file_size as usize).min(4 * 1024 * 1024); // Max 4 MB
-        let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+        let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
         let mut buf = alloc::vec![0u8; read_size];
         let bytes_read = match fat.read_file_sized(file_cluster, read_size, &mut buf) {
             Ok(n) => n,
@@ -29463,7 +32629,7 @@ impl Compositor {
                             crate::audio::status_text()
                         );
                         // Show full init log in notepad content for diagnosis
-                        win.notepad_text = init_log;
+                        win.notepad_area.set_text(init_log.as_str());
                     }
                 }
             }
@@ -29493,7 +32659,7 @@ impl Compositor {
                                     "HDA: {} — archivo decodificado pero no se puede reproducir.",
                                     crate::audio::status_text()
                                 );
-                                win.notepad_text = init_log;
+                                win.notepad_area.set_text(init_log.as_str());
                             }
                         }
                     }
@@ -30908,6 +34074,61 @@ impl Compositor {
         Some(close_pos + 1)
     }
 
+    /// `beep(hz, ms)`, `play_note(key, ms[, program])` and `play_sound("name")`
+    /// become one sound op whose text is a `synth::run_script_op` command.
+    fn ide_apply_sound_call(
+        stmt: &str,
+        vars: &[IdeRuntimeVar],
+        ops: &mut Vec<(String, String)>,
+    ) -> Option<usize> {
+        let trimmed = stmt.trim_start();
+        let lower = Self::ascii_lower(trimmed);
+        let (marker_len, kind) = if lower.starts_with("play_sound") {
+            ("play_sound".len(), "sound")
+        } else if lower.starts_with("play_note") {
+            ("play_note".len(), "note")
+        } else if lower.starts_with("beep") {
+            ("beep".len(), "beep")
+        } else {
+            return None;
+        };
+
+        let mut open_pos = marker_len;
+        while let Some(ch) = Self::ide_char_at(trimmed, open_pos) {
+            if ch.is_whitespace() {
+                open_pos = Self::ide_next_char_pos(trimmed, open_pos);
+            } else {
+                break;
+            }
+        }
+        if Self::ide_char_at(trimmed, open_pos) != Some('(') {
+            return None;
+        }
+        let close_pos = Self::ide_find_matching_delim(trimmed, open_pos, '(', ')')?;
+        if close_pos <= open_pos {
+            return None;
+        }
+
+        let inside = &trimmed[open_pos + 1..close_pos];
+        let args = Self::ide_split_call_args(inside);
+        let mut op = String::from(kind);
+        for arg in args.iter().take(3) {
+            let value = if kind == "sound" {
+                Self::ide_unquote_arg(arg.as_str())
+                    .unwrap_or_else(|| Self::ide_runtime_to_text(&Self::ide_eval_expr(arg.as_str(), vars)))
+            } else {
+                alloc::format!(
+                    "{}",
+                    Self::ide_runtime_to_i64(&Self::ide_eval_expr(arg.as_str(), vars)).max(0)
+                )
+            };
+            op.push(' ');
+            op.push_str(value.trim());
+        }
+        ops.push((String::from(IDE_RUNTIME_SOUND_OP_ID), op));
+        Some(close_pos + 1)
+    }
+
     fn ide_parse_delay_ms_text(text: &str) -> u64 {
         let t = text.trim();
         if t.is_empty() {
@@ -31054,6 +34275,19 @@ impl Compositor {
                 continue;
             }
 
+            if target_id.eq_ignore_ascii_case(IDE_RUNTIME_SOUND_OP_ID) {
+                // Let the sound finish before the next step, so scripted
+                // melodies keep their rhythm.
+                let sound_ms = crate::synth::run_script_op(text.as_str()) as u64;
+                let has_more_steps = ops[idx + 1..]
+                    .iter()
+                    .any(|(target, _)| !target.eq_ignore_ascii_case(IDE_RUNTIME_DELAY_OP_ID));
+                if has_more_steps && sound_ms > 0 {
+                    uefi::boot::stall(sound_ms.min(IDE_RUNTIME_DELAY_MAX_MS) as usize * 1000);
+                }
+                continue;
+            }
+
             if target_id.eq_ignore_ascii_case(IDE_RUNTIME_SET_VIEW_OP_ID) {
                 let changed = match scope {
                     IdeRuntimeScope::Preview => self.ide_switch_preview_view(win_id, text.as_str()),
@@ -31199,6 +34433,16 @@ impl Compositor {
             return IdeExecFlow::Next;
         }
 
+        if let Some(consumed) = Self::ide_apply_sound_call(trimmed, vars.as_slice(), ops) {
+            if consumed <= trimmed.len() {
+                let tail = trimmed[consumed..].trim();
+                if !tail.is_empty() {
+                    return Self::ide_exec_simple_stmt(tail, vars, ops, in_loop, budget);
+                }
+            }
+            return IdeExecFlow::Next;
+        }
+
         if let Some(consumed) = Self::ide_apply_set_view_call(trimmed, vars.as_slice(), ops) {
             if consumed <= trimmed.len() {
                 let tail = trimmed[consumed..].trim();
@@ -32768,20 +36012,43 @@ impl Compositor {
         vars.push((String::from(name), value));
     }
 
+    /// The answer to `json_get(url)` once its request is done. Until then
+    /// the request runs on an executor task and this returns
+    /// `IDE_RUST_JSON_PENDING`; `service_ide_json_fetches` runs the script
+    /// again when the answer is in.
     fn ide_rust_fetch_json_text(&mut self, url: &str) -> Result<String, String> {
-        let request_urls = Self::build_fetch_url_candidates(url);
-        if request_urls.is_empty() {
-            return Err(String::from("URL invalida para json_get()."));
+        if let Some((_, result)) = self.ide_json_results.iter().find(|(done, _)| done == url) {
+            let result = result.clone();
+            self.ide_json_used.push(String::from(url));
+            return result;
         }
+        if !self.ide_json_fetches.iter().any(|fetch| fetch.url == url) {
+            let request_urls = Self::build_fetch_url_candidates(url);
+            if request_urls.is_empty() {
+                return Err(String::from("URL invalida para json_get()."));
+            }
+            let Some(job) = crate::executor::spawn_joinable("ide-json-get", Self::ide_rust_json_get(request_urls))
+            else {
+                return Err(String::from("sin tareas libres para json_get()."));
+            };
+            self.ide_json_fetches.push(IdeJsonFetch {
+                url: String::from(url),
+                job,
+            });
+        }
+        Err(String::from(IDE_RUST_JSON_PENDING))
+    }
 
+    async fn ide_rust_json_get(request_urls: Vec<String>) -> Result<String, String> {
         let mut last_err = String::from("sin respuesta");
         for candidate in request_urls.iter() {
-            let mut pump = || self.pump_ui_while_blocked_net();
-            let Some(raw) = crate::net::http_get_request_with_timeout(
-                candidate.as_str(),
-                &mut pump,
-                WEB_PROXY_FRAME_TIMEOUT_TICKS,
-            ) else {
+            let request =
+                crate::net::request::HttpRequest::get(candidate.as_str()).timeout_ms(WEB_PROXY_FRAME_TIMEOUT_MS);
+            let response = match crate::net::worker::fetch(request) {
+                Some(fetch) => fetch.await,
+                None => None,
+            };
+            let Some(raw) = response.map(|bytes| String::from_utf8_lossy(bytes.as_slice()).into_owned()) else {
                 last_err = alloc::format!("sin respuesta de {}", candidate);
                 continue;
             };
@@ -32876,6 +36143,40 @@ impl Compositor {
         rust_src: &str,
         label: &str,
         reset_preview: bool,
+    ) -> Result<Option<String>, String> {
+        let result = self.ide_rust_run_ui_bridge_block_once(scope, win_id, rust_src, label, reset_preview);
+        if matches!(&result, Err(err) if err.as_str() == IDE_RUST_JSON_PENDING) {
+            self.ide_json_used.clear();
+            let waiting = self
+                .ide_runs_waiting
+                .iter()
+                .any(|run| run.win_id == win_id && run.scope == scope && run.source == rust_src);
+            if !waiting {
+                self.ide_runs_waiting.push(IdeRunWaiting {
+                    scope,
+                    win_id,
+                    source: String::from(rust_src),
+                    label: String::from(label),
+                    reset_preview,
+                });
+            }
+            return Ok(Some(alloc::format!(
+                "Rust UI bridge [{}]: esperando json_get...",
+                label
+            )));
+        }
+        let used = core::mem::take(&mut self.ide_json_used);
+        self.ide_json_results.retain(|(url, _)| !used.contains(url));
+        result
+    }
+
+    fn ide_rust_run_ui_bridge_block_once(
+        &mut self,
+        scope: IdeRuntimeScope,
+        win_id: usize,
+        rust_src: &str,
+        label: &str,
+        reset_preview: bool,
     ) -> Result<Option<String>, String> {
         if rust_src.trim().is_empty() {
             return Ok(None);
@@ -33267,7 +36568,7 @@ impl Compositor {
         let selected_device_index = target_device_index;
         let mut switched_device = false;
         let load_result = (|| -> Result<(u32, String, String, String, String, String, String, String), String> {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             let mut dir_cluster = target_cluster;
             let mut dir_path = target_path_hint
                 .map(|v| String::from(v.trim()))
@@ -33525,7 +36826,7 @@ impl Compositor {
         let mut switched_device = false;
         let mut shortcut_to_register: Option<(String, String)> = None;
         let write_result = (|| {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             if export_mode {
                 let rpx = match Self::ide_build_rpx_blob(&[
                     ("main.rs", rust_src.as_bytes()),
@@ -33841,18 +37142,14 @@ impl Compositor {
         }
     }
 
-    fn open_notepad_from_explorer_file(
-        &mut self,
-        dir_cluster: u32,
-        dir_path: String,
-        item: &ExplorerItem,
-    ) {
+    /// Text of `item` for the notepad, and the status line to show with it.
+    fn read_notepad_text(&mut self, item: &ExplorerItem) -> (String, String) {
         let mut text = String::new();
         let mut status = alloc::format!("Opened {}", item.label);
 
         if self.ensure_fat_ready() {
             if item.cluster >= 2 && item.size > 0 {
-                let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 let max_load = item.size as usize;
                 let target = max_load.min(NOTEPAD_MAX_TEXT_BYTES);
                 let mut buffer = Vec::new();
@@ -33884,6 +37181,16 @@ impl Compositor {
         } else {
             status = String::from("FAT32 not ready.");
         }
+        (text, status)
+    }
+
+    fn open_notepad_from_explorer_file(
+        &mut self,
+        dir_cluster: u32,
+        dir_path: String,
+        item: &ExplorerItem,
+    ) {
+        let (text, status) = self.read_notepad_text(item);
 
         let note_id = self.create_notepad_window("Notepad", 180, 90, 860, 560);
         if let Some(win) = self.windows.iter_mut().find(|w| w.id == note_id) {
@@ -33918,7 +37225,7 @@ impl Compositor {
                 win.notepad_dir_cluster,
                 win.notepad_dir_path.clone(),
                 win.notepad_file_name.clone(),
-                win.notepad_text.clone(),
+                String::from(win.notepad_area.text()),
             ),
             None => return,
         };
@@ -33943,7 +37250,7 @@ impl Compositor {
         }
 
         {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             if dir_cluster < 2 {
                 dir_cluster = fat.root_cluster;
                 let label = Self::volume_label_text(fat).unwrap_or(String::from("USB"));
@@ -33952,7 +37259,7 @@ impl Compositor {
         }
 
         let result = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             fat.write_text_file_in_dir(dir_cluster, trimmed_name, text.as_bytes())
         };
         let mut saved_ok = false;
@@ -33970,7 +37277,7 @@ impl Compositor {
         }
         if saved_ok {
             let saved_cluster = {
-                let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 match fat.resolve_path(dir_cluster, trimmed_name) {
                     Ok((_, cluster)) => {
                         if cluster == 0 {
@@ -34001,8 +37308,8 @@ impl Compositor {
     }
 
     fn delete_notepad_file(&mut self, win_id: usize) {
-        let (mut dir_cluster, file_name) = match self.windows.iter().find(|w| w.id == win_id) {
-            Some(win) => (win.notepad_dir_cluster, win.notepad_file_name.clone()),
+        let (mut dir_cluster, dir_path, file_name) = match self.windows.iter().find(|w| w.id == win_id) {
+            Some(win) => (win.notepad_dir_cluster, win.notepad_dir_path.clone(), win.notepad_file_name.clone()),
             None => return,
         };
 
@@ -34014,19 +37321,33 @@ impl Compositor {
             return;
         }
 
+        if let Some(dir) = Self::mounted_dialog_dir(dir_cluster, dir_path.as_str()) {
+            let result = crate::fs::vfs::remove(crate::fs::vfs::join(dir.as_str(), trimmed_name).as_str());
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                match result {
+                    Ok(()) => {
+                        win.notepad_area.clear();
+                        win.set_notepad_status("File deleted.");
+                    }
+                    Err(e) => win.set_notepad_status(alloc::format!("Delete failed: {}", e).as_str()),
+                }
+            }
+            return;
+        }
+
         if !self.ensure_fat_ready_for_notepad(win_id) {
             return;
         }
 
         {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             if dir_cluster < 2 {
                 dir_cluster = fat.root_cluster;
             }
         }
 
         let result = {
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             fat.delete_file_in_dir(dir_cluster, trimmed_name)
         };
 
@@ -34034,7 +37355,7 @@ impl Compositor {
             win.notepad_dir_cluster = dir_cluster;
             match result {
                 Ok(()) => {
-                    win.notepad_text.clear();
+                    win.notepad_area.clear();
                     win.set_notepad_status("File deleted.");
                 }
                 Err(e) => win.set_notepad_status(alloc::format!("Delete failed: {}", e).as_str()),
@@ -34054,8 +37375,10 @@ impl Compositor {
                     win.prepare_notepad_new("NEWFILE.TXT");
                 }
             }
+            Some(NotepadClickAction::Open) => self.begin_notepad_open_dialog(win_id),
             Some(NotepadClickAction::Save) => self.begin_notepad_save_prompt(win_id),
-            Some(NotepadClickAction::Delete) => self.delete_notepad_file(win_id),
+            Some(NotepadClickAction::Delete) => self.confirm_notepad_delete(win_id),
+            Some(NotepadClickAction::Print) => self.begin_print_dialog(win_id),
             Some(NotepadClickAction::FilenameField) => {
                 if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
                     win.set_notepad_filename_focus(true);
@@ -34063,7 +37386,8 @@ impl Compositor {
             }
             Some(NotepadClickAction::EditorArea) => {
                 if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
-                    win.set_notepad_filename_focus(false);
+                    win.notepad_press_at(mouse_x, mouse_y);
+                    self.notepad_selection_drag = Some(win_id);
                 }
             }
             None => {}
@@ -34240,10 +37564,27 @@ impl Compositor {
                     }
                     win.render_explorer();
                 }
-                let selected_count = self
-                    .explorer_collect_selected_items(win_id, dir_cluster, items.as_slice())
-                    .len()
-                    .max(1);
+                let selection = self.explorer_collect_selected_items(win_id, dir_cluster, items.as_slice());
+                let device_index = self
+                    .windows
+                    .iter()
+                    .find(|w| w.id == win_id)
+                    .and_then(|w| w.explorer_device_index)
+                    .or(self.current_volume_device_index);
+                let dragged: Vec<FileRef> = selection
+                    .iter()
+                    .map(|selected| FileRef {
+                        device_index,
+                        dir_cluster,
+                        dir_path: dir_path.clone(),
+                        cluster: selected.cluster,
+                        size: selected.size,
+                        is_directory: selected.kind == ExplorerItemKind::Directory,
+                        name: selected.label.clone(),
+                    })
+                    .collect();
+                self.offer_drag(win_id, Payload::Files(dragged), mouse_x, mouse_y);
+                let selected_count = selection.len().max(1);
 
                 if item.kind == ExplorerItemKind::Directory {
                     if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
@@ -34304,6 +37645,8 @@ impl Compositor {
                     }
                 } else if Self::is_png_file_name(item.label.as_str()) {
                     self.open_png_from_explorer_file(win_id, &item);
+                } else if Self::is_pdf_file_name(item.label.as_str()) {
+                    self.open_pdf_from_explorer_file(win_id, &item);
                 } else if Self::is_audio_file_name(item.label.as_str()) {
                     self.open_media_player_file(item.cluster, item.label.as_str(), item.size);
                 } else if Self::is_video_file_name(item.label.as_str()) {
@@ -34438,24 +37781,22 @@ impl Compositor {
         out
     }
 
-    fn web_http_get_short(&mut self, url: &str) -> Option<String> {
-        let mut pump = || self.pump_ui_while_blocked_net();
-        crate::net::http_get_request_with_timeout(url, &mut pump, WEB_PROXY_PROBE_TIMEOUT_TICKS)
+    async fn web_http_get_short(url: &str) -> Option<String> {
+        let request = crate::net::request::HttpRequest::get(url).timeout_ms(WEB_PROXY_PROBE_TIMEOUT_MS);
+        let raw = crate::net::worker::fetch(request)?.await?;
+        Some(String::from_utf8_lossy(raw.as_slice()).into_owned())
     }
 
-    fn web_cef_request_first_reachable(
-        &mut self,
-        path_and_query: &str,
-    ) -> (Option<String>, Option<String>, Vec<String>) {
-        let candidates = self.web_proxy_candidate_bases();
+    /// The first of `candidates` that answers `path_and_query`, with its
+    /// reply. The caller remembers it in `web_proxy_endpoint_base`.
+    async fn web_cef_request_first_reachable(candidates: &[String], path_and_query: &str) -> Option<(String, String)> {
         for base in candidates.iter() {
             let endpoint = Self::web_proxy_url_with_base(base.as_str(), path_and_query);
-            if let Some(raw) = self.web_http_get_short(endpoint.as_str()) {
-                self.web_proxy_endpoint_base = base.clone();
-                return (Some(base.clone()), Some(raw), candidates);
+            if let Some(raw) = Self::web_http_get_short(endpoint.as_str()).await {
+                return Some((base.clone(), raw));
             }
         }
-        (None, None, candidates)
+        None
     }
 
     fn hex_upper(n: u8) -> char {
@@ -34608,17 +37949,10 @@ impl Compositor {
         })
     }
 
-    fn browser_fetch_cef_frame_with_base(
-        &mut self,
-        base: &str,
-    ) -> Option<crate::web_servo_bridge::ServoBridgeSurface> {
+    async fn browser_fetch_cef_frame_with_base(base: &str) -> Option<crate::web_servo_bridge::ServoBridgeSurface> {
         let endpoint = Self::web_proxy_url_with_base(base, "frame");
-        let mut pump = || self.pump_ui_while_blocked_net();
-        let raw = crate::net::http_get_request_bytes_with_timeout(
-            endpoint.as_str(),
-            &mut pump,
-            WEB_PROXY_FRAME_TIMEOUT_TICKS,
-        )?;
+        let request = crate::net::request::HttpRequest::get(endpoint.as_str()).timeout_ms(WEB_PROXY_FRAME_TIMEOUT_MS);
+        let raw = crate::net::worker::fetch(request)?.await?;
         let (code, body) = Self::parse_http_status_and_body_bytes(raw.as_slice());
         if code != Some(200) {
             return None;
@@ -34626,14 +37960,44 @@ impl Compositor {
         Self::parse_ppm_p6_surface(body.as_slice(), "webkit-host-frame")
     }
 
+    /// Send input to the WebKit bridge on an executor task;
+    /// `browser_cef_show_input` applies the reply.
     fn browser_cef_dispatch_input(&mut self, win_id: usize, path_and_query: &str) {
-        let (base, raw, tried_bases) = self.web_cef_request_first_reachable(path_and_query);
-        let Some(raw) = raw else {
+        let candidates = self.web_proxy_candidate_bases();
+        let path_and_query = String::from(path_and_query);
+        let job = crate::executor::spawn_joinable("webkit-input", async move {
+            let reply = Self::web_cef_request_first_reachable(candidates.as_slice(), path_and_query.as_str()).await;
+            let mut surface = None;
+            if let Some((base, raw)) = reply.as_ref() {
+                if Self::parse_http_status_and_body(raw.as_str()).0 == Some(200) {
+                    surface = Self::browser_fetch_cef_frame_with_base(base.as_str()).await;
+                }
+            }
+            BridgeInputSent {
+                reply,
+                tried: candidates,
+                surface,
+            }
+        });
+        match job {
+            Some(job) => self.browser_bridge_inputs.push(BrowserBridgeInput { win_id, job }),
+            None => {
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    win.browser_status = String::from("WEBKIT input: sin tareas libres");
+                    win.render_browser();
+                }
+                self.paint();
+            }
+        }
+    }
+
+    fn browser_cef_show_input(&mut self, win_id: usize, sent: BridgeInputSent) {
+        let Some((selected_base, raw)) = sent.reply else {
             if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
                 win.browser_status = String::from("WEBKIT input offline");
                 win.browser_content_lines
                     .push(String::from("[WEBKIT] input fallo: endpoint no alcanzable."));
-                for base in tried_bases.iter().take(3) {
+                for base in sent.tried.iter().take(3) {
                     win.browser_content_lines
                         .push(alloc::format!("[WEBKIT] tried: {}", base));
                 }
@@ -34643,11 +38007,11 @@ impl Compositor {
             return;
         };
 
-        let selected_base = base.unwrap_or_else(|| self.web_proxy_base());
+        self.web_proxy_endpoint_base = selected_base;
         let (code, body) = Self::parse_http_status_and_body(raw.as_str());
         let mut status = alloc::format!("WEBKIT input HTTP {:?}", code);
         if code == Some(200) {
-            if let Some(surface) = self.browser_fetch_cef_frame_with_base(selected_base.as_str()) {
+            if let Some(surface) = sent.surface {
                 if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
                     win.browser_surface_source = surface.source;
                     win.browser_surface_width = surface.width;
@@ -34684,12 +38048,16 @@ impl Compositor {
         self.paint();
     }
 
-    fn browser_fetch_with_cef_bridge(&mut self, url: &str) -> crate::web_servo_bridge::ServoBridgeRender {
+    /// Open `url` in the WebKit host bridge, trying `tried_bases` in turn.
+    async fn browser_fetch_with_cef_bridge(url: String, tried_bases: Vec<String>) -> BrowserLoaded {
+        let url = url.as_str();
         let encoded_url = Self::url_encode_component(url);
-        let (open_base, open_raw, tried_bases) = self.web_cef_request_first_reachable(
+        let open = Self::web_cef_request_first_reachable(
+            tried_bases.as_slice(),
             alloc::format!("open?url={}", encoded_url).as_str(),
-        );
-        let Some(open_raw) = open_raw else {
+        )
+        .await;
+        let Some((selected_base, open_raw)) = open else {
             let mut lines = vec![
                 String::from("[HOST] No se pudo conectar al renderer HTTPS."),
                 String::from("[HOST] GO no usa fallback texto en modo host."),
@@ -34714,7 +38082,7 @@ impl Compositor {
                     lines.push(alloc::format!("  - {}", base));
                 }
             }
-            return crate::web_servo_bridge::ServoBridgeRender {
+            let render = crate::web_servo_bridge::ServoBridgeRender {
                 output: Some(crate::web_engine::BrowserRenderOutput {
                     final_url: String::from(url),
                     status: String::from("HOST BRIDGE OFFLINE"),
@@ -34728,12 +38096,12 @@ impl Compositor {
                 )),
                 surface: None,
             };
+            return BrowserLoaded { render, endpoint: None };
         };
 
-        let selected_base = open_base.unwrap_or_else(|| self.web_proxy_base());
         let (open_code, open_body) = Self::parse_http_status_and_body(open_raw.as_str());
         if open_code != Some(200) {
-            return crate::web_servo_bridge::ServoBridgeRender {
+            let render = crate::web_servo_bridge::ServoBridgeRender {
                 output: Some(crate::web_engine::BrowserRenderOutput {
                     final_url: String::from(url),
                     status: String::from("HOST BRIDGE ERROR"),
@@ -34752,6 +38120,10 @@ impl Compositor {
                 )),
                 surface: None,
             };
+            return BrowserLoaded {
+                render,
+                endpoint: Some(selected_base),
+            };
         }
 
         let mut lines = Vec::new();
@@ -34767,7 +38139,7 @@ impl Compositor {
         }
 
         let status_endpoint = Self::web_proxy_url_with_base(selected_base.as_str(), "status");
-        let status_raw = self.web_http_get_short(status_endpoint.as_str());
+        let status_raw = Self::web_http_get_short(status_endpoint.as_str()).await;
         if let Some(status_raw) = status_raw {
             let (status_code, status_body) = Self::parse_http_status_and_body(status_raw.as_str());
             lines.push(String::new());
@@ -34784,7 +38156,7 @@ impl Compositor {
             lines.push(String::from("[WEBKIT] solicitud enviada."));
         }
 
-        let surface = self.browser_fetch_cef_frame_with_base(selected_base.as_str());
+        let surface = Self::browser_fetch_cef_frame_with_base(selected_base.as_str()).await;
         if let Some(surface) = surface.as_ref() {
             lines.push(alloc::format!(
                 "[WEBKIT] frame: {}x{} ({})",
@@ -34796,7 +38168,7 @@ impl Compositor {
             ));
         }
 
-        crate::web_servo_bridge::ServoBridgeRender {
+        let render = crate::web_servo_bridge::ServoBridgeRender {
             output: Some(crate::web_engine::BrowserRenderOutput {
                 final_url: String::from(url),
                 status: String::from("WEBKIT BRIDGE OK"),
@@ -34806,6 +38178,10 @@ impl Compositor {
             }),
             note: Some(String::from("render remoto via WebKit/Wry host HTTP bridge.")),
             surface,
+        };
+        BrowserLoaded {
+            render,
+            endpoint: Some(selected_base),
         }
     }
 
@@ -35280,6 +38656,22 @@ impl Compositor {
         win_id: usize,
         url: &str,
     ) -> crate::web_servo_bridge::ServoBridgeRender {
+        // Gemini/Gopher only exist in the builtin engine; the HTML backends
+        // would try to fetch them over HTTP.
+        let lower = Self::ascii_lower(url.trim());
+        if lower.starts_with("gemini://") || lower.starts_with("gopher://") {
+            let mut pump = || self.pump_ui_while_blocked_net();
+            let output = crate::web_engine::fetch_and_render(url, &mut pump);
+            let surface = output
+                .as_ref()
+                .and_then(crate::web_servo_bridge::builtin_surface_from_output);
+            return crate::web_servo_bridge::ServoBridgeRender {
+                output,
+                note: Some(String::from("cliente Gemini/Gopher interno.")),
+                surface,
+            };
+        }
+
         match self.web_backend_mode {
             WebBackendMode::Builtin => {
                 let mut pump = || self.pump_ui_while_blocked_net();
@@ -35307,7 +38699,13 @@ impl Compositor {
             WebBackendMode::ServoRt => self.browser_fetch_with_servort(win_id, url),
             WebBackendMode::Cef => {
                 if WEB_CEF_BRIDGE_ENABLED {
-                    self.browser_fetch_with_cef_bridge(url)
+                    let load = Self::browser_fetch_with_cef_bridge(String::from(url), self.web_proxy_candidate_bases());
+                    let mut pump = || self.pump_ui_while_blocked_net();
+                    let loaded = crate::executor::block_on_with(load, &mut pump);
+                    if let Some(endpoint) = loaded.endpoint {
+                        self.web_proxy_endpoint_base = endpoint;
+                    }
+                    loaded.render
                 } else {
                     let mut pump = || self.pump_ui_while_blocked_net();
                     let output = crate::web_engine::fetch_and_render(url, &mut pump);
@@ -35327,6 +38725,40 @@ impl Compositor {
         }
     }
 
+    /// Start loading `url` on an executor task for the backends that can:
+    /// the builtin engine and the WebKit bridge. `None` for the others,
+    /// which `browser_fetch_with_backend` loads in place.
+    fn browser_spawn_load(&mut self, url: &str) -> Option<crate::executor::JoinHandle<BrowserLoaded>> {
+        let lower = Self::ascii_lower(url.trim());
+        if lower.starts_with("gemini://") || lower.starts_with("gopher://") {
+            return None;
+        }
+        match self.web_backend_mode {
+            WebBackendMode::Builtin => {
+                let url = String::from(url);
+                crate::executor::spawn_joinable("browser-load", async move {
+                    let output = crate::web_engine::load(url).await;
+                    let surface = output
+                        .as_ref()
+                        .and_then(crate::web_servo_bridge::builtin_surface_from_output);
+                    let render = crate::web_servo_bridge::ServoBridgeRender {
+                        output,
+                        note: Some(String::from(
+                            "render interno builtin (HTML/CSS/JS subset visual) sin proxy.",
+                        )),
+                        surface,
+                    };
+                    BrowserLoaded { render, endpoint: None }
+                })
+            }
+            WebBackendMode::Cef if WEB_CEF_BRIDGE_ENABLED => crate::executor::spawn_joinable(
+                "browser-load",
+                Self::browser_fetch_with_cef_bridge(String::from(url), self.web_proxy_candidate_bases()),
+            ),
+            _ => None,
+        }
+    }
+
     fn browser_navigate_to(&mut self, win_id: usize, target_url: &str) {
         let url = target_url.trim();
         if url.is_empty() {
@@ -35353,12 +38785,10 @@ impl Compositor {
         // Force paint so user sees immediate feedback.
         self.paint();
 
-        let link_up = unsafe {
-            if crate::intel_net::GLOBAL_INTEL_NET.is_some() {
-                crate::intel_net::is_link_up()
-            } else {
-                true // Assume VirtIO link is up if device exists
-            }
+        let link_up = if crate::intel_net::is_present() {
+            crate::intel_net::is_link_up()
+        } else {
+            true // Assume VirtIO link is up if device exists
         };
 
         if !link_up && !url.starts_with("redux://") {
@@ -35376,9 +38806,27 @@ impl Compositor {
         let render_result = if url.starts_with("redux://") {
             None
         } else {
+            // A new page replaces one still loading in the same window.
+            self.browser_loads.retain(|load| load.win_id != win_id);
+            if let Some(job) = self.browser_spawn_load(url) {
+                self.browser_loads.push(BrowserLoad {
+                    win_id,
+                    url: String::from(url),
+                    job,
+                });
+                return;
+            }
             Some(self.browser_fetch_with_backend(win_id, url))
         };
+        self.browser_show_render(win_id, url, render_result);
+    }
 
+    fn browser_show_render(
+        &mut self,
+        win_id: usize,
+        url: &str,
+        render_result: Option<crate::web_servo_bridge::ServoBridgeRender>,
+    ) {
         // Update window with render result.
         if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
             if let Some(mut result) = render_result {
@@ -35466,6 +38914,8 @@ impl Compositor {
     pub fn handle_browser_click(&mut self, win_id: usize, mouse_x: i32, mouse_y: i32) {
         enum BrowserClickAction {
             Navigate(String),
+            Download,
+            Print,
             ScrollRows(i32),
             CefInput(String),
             VaevInput(crate::web_vaev_bridge::VaevInputEvent),
@@ -35507,6 +38957,10 @@ impl Compositor {
                 }
             } else if win.browser_go_clicked(mouse_x, mouse_y) {
                 BrowserClickAction::Navigate(win.browser_url.clone())
+            } else if win.browser_download_clicked(mouse_x, mouse_y) {
+                BrowserClickAction::Download
+            } else if win.browser_print_clicked(mouse_x, mouse_y) {
+                BrowserClickAction::Print
             } else if use_cef && win.browser_back_clicked(mouse_x, mouse_y) {
                 BrowserClickAction::CefInput(String::from("input?type=back"))
             } else if use_vaev && win.browser_back_clicked(mouse_x, mouse_y) {
@@ -35554,6 +39008,8 @@ impl Compositor {
             BrowserClickAction::Navigate(url) => {
                 self.browser_navigate_to(win_id, url.as_str());
             }
+            BrowserClickAction::Download => self.begin_browser_download(win_id),
+            BrowserClickAction::Print => self.begin_print_dialog(win_id),
             BrowserClickAction::ScrollRows(step) => {
                 if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
                     if win.browser_scroll_by(step) {
@@ -35609,6 +39065,9 @@ impl Compositor {
     }
 
     fn handle_settings_click(&mut self, win_id: usize, mouse_x: i32, mouse_y: i32) {
+        if self.handle_settings_display_click(win_id, mouse_x, mouse_y) {
+            return;
+        }
         let should_open_wifi = {
             let Some(win) = self.windows.iter().find(|w| w.id == win_id) else {
                 return;
@@ -35641,6 +39100,48 @@ impl Compositor {
         }
     }
 
+    /// Gamma / temperature sliders and the night light toggle at the top of
+    /// Settings. Returns whether the click landed on one of them.
+    fn handle_settings_display_click(&mut self, win_id: usize, mouse_x: i32, mouse_y: i32) -> bool {
+        use crate::gui::window::{
+            SETTINGS_GAMMA_ROW_Y, SETTINGS_NIGHT_ROW_Y, SETTINGS_ROW_H, SETTINGS_SLIDER_W, SETTINGS_SLIDER_X,
+            SETTINGS_TEMPERATURE_ROW_Y,
+        };
+        let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) else {
+            return false;
+        };
+        if !win.is_settings() {
+            return false;
+        }
+        let rx = mouse_x - win.rect.x;
+        let ry = mouse_y - (win.rect.y + crate::gui::window::TITLE_BAR_H);
+        let on_row = |row_y: i32| ry >= row_y - 2 && ry < row_y + SETTINGS_ROW_H;
+        let on_slider = (SETTINGS_SLIDER_X - 4..=SETTINGS_SLIDER_X + SETTINGS_SLIDER_W + 4).contains(&rx);
+        // Position along the slider scaled to `range`, rounded to `step`.
+        let slider_value = |range: u32, step: u32| {
+            let offset = (rx - SETTINGS_SLIDER_X).clamp(0, SETTINGS_SLIDER_W) as u32;
+            (offset * range / SETTINGS_SLIDER_W as u32 + step / 2) / step * step
+        };
+        let result = if on_slider && on_row(SETTINGS_GAMMA_ROW_Y) {
+            let range = crate::gamma::GAMMA_MAX - crate::gamma::GAMMA_MIN;
+            crate::gamma::set_gamma(crate::gamma::GAMMA_MIN + slider_value(range, 5))
+        } else if on_slider && on_row(SETTINGS_TEMPERATURE_ROW_Y) {
+            let range = crate::gamma::NEUTRAL_TEMPERATURE - crate::gamma::TEMPERATURE_MIN;
+            crate::gamma::set_temperature(crate::gamma::TEMPERATURE_MIN + slider_value(range, 100))
+        } else if (15..=SETTINGS_SLIDER_X + SETTINGS_SLIDER_W).contains(&rx) && on_row(SETTINGS_NIGHT_ROW_Y) {
+            let (_, _, night, _) = crate::gamma::settings_snapshot();
+            crate::gamma::set_night_mode(night.next())
+        } else {
+            return false;
+        };
+        if let Err(e) = result {
+            crate::klog::log(crate::klog::Level::Warning, alloc::format!("settings: gamma: {}", e).as_str());
+        }
+        win.render_settings();
+        self.mark_dirty();
+        true
+    }
+
     fn handle_wifi_manager_click(&mut self, win_id: usize, mouse_x: i32, mouse_y: i32) {
         let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) else {
             return;
@@ -35790,6 +39291,46 @@ impl Compositor {
         }
     }
 
+    /// "Terminar" on the selected row. The desktop acts with user privilege,
+    /// so the kernel row and ring-0 processes are refused.
+    fn task_manager_kill(&mut self, target: Option<TaskManagerTarget>) -> String {
+        let Some(target) = target else {
+            return String::from("Selecciona una tarea.");
+        };
+        match target {
+            TaskManagerTarget::Process(pid) => {
+                let result = if pid == 0 {
+                    Err(crate::process::KillError::Privilege)
+                } else {
+                    crate::process::kill(pid, crate::process::RingLevel::User)
+                };
+                match result {
+                    Ok(()) => {
+                        crate::sysmon::refresh();
+                        alloc::format!("Proceso {} terminado.", pid)
+                    }
+                    Err(err) => alloc::format!("No se pudo terminar {}: {}.", pid, err.message()),
+                }
+            }
+            TaskManagerTarget::Install => String::from(if self.cancel_install_tasks() {
+                "Cancelando instalaciones..."
+            } else {
+                "No hay instalaciones activas."
+            }),
+            TaskManagerTarget::FsTask => String::from(if self.cancel_terminal_fs_tasks() {
+                "Cancelando tareas FS..."
+            } else {
+                "No hay tareas FS activas."
+            }),
+            TaskManagerTarget::Paste => String::from(if self.cancel_clipboard_paste_tasks() {
+                "Cancelando paste..."
+            } else {
+                "No hay paste activo."
+            }),
+            TaskManagerTarget::LinuxRunloop => String::from("El runloop Linux no se puede terminar desde aqui."),
+        }
+    }
+
     fn handle_task_manager_click(&mut self, win_id: usize, mouse_x: i32, mouse_y: i32) -> bool {
         let action = {
             let Some(win) = self.windows.iter().find(|w| w.id == win_id) else {
@@ -35809,17 +39350,34 @@ impl Compositor {
         match action {
             TaskManagerClickAction::Select(idx) => {
                 if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
-                    win.task_manager_selected = Some(idx);
-                    if let Some(line) = win.task_manager_lines.get(idx) {
-                        status = Some(Self::trim_ascii_line(line.as_str(), 48));
-                    }
-                    if let Some(text) = status.take() {
-                        win.task_manager_status = text;
+                    win.task_manager_list.select(Some(idx));
+                    if let Some(cells) = win.task_manager_list.rows().get(idx) {
+                        let line = alloc::format!("{} {}", cells[0], cells[1]);
+                        win.task_manager_status = Self::trim_ascii_line(line.as_str(), 48);
                     }
                     win.render();
                 }
                 return true;
             }
+            TaskManagerClickAction::Sort(key) => {
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    win.task_manager_set_sort(key);
+                }
+                return true;
+            }
+            TaskManagerClickAction::Kill => {
+                let target = self
+                    .windows
+                    .iter()
+                    .find(|w| w.id == win_id)
+                    .and_then(|w| w.task_manager_selected_target());
+                status = Some(self.task_manager_kill(target));
+                let processes = crate::sysmon::process_rows();
+                for win in self.windows.iter_mut().filter(|w| w.is_task_manager()) {
+                    win.task_manager_processes = processes.clone();
+                    win.rebuild_task_manager_list();
+                }
+            }
             TaskManagerClickAction::CancelInstall => {
                 let ok = self.cancel_install_tasks();
                 status = Some(String::from(if ok {
@@ -35895,7 +39453,11 @@ impl Compositor {
             if !self.windows[i].rect.contains(Point { x: mouse_x, y: mouse_y }) {
                 continue;
             }
-            if !self.windows[i].is_task_manager() {
+            if !self.windows[i].is_task_manager()
+                && !self.windows[i].is_boot_entries()
+                && !self.windows[i].is_pdf_viewer()
+                && !self.windows[i].is_applet()
+            {
                 continue;
             }
 
@@ -35903,6 +39465,9 @@ impl Compositor {
             let changed = {
                 let win = &mut self.windows[i];
                 win.task_manager_scroll_by(delta_rows)
+                    || win.boot_entries_scroll_by(delta_rows)
+                    || win.pdf_viewer_scroll_by(delta_rows)
+                    || win.applet_scroll_by(delta_rows)
             };
             if changed {
                 self.active_window_id = Some(win_id);
@@ -35913,6 +39478,453 @@ impl Compositor {
         false
     }
 
+    fn handle_pdf_viewer_click(&mut self, win_id: usize, mouse_x: i32, mouse_y: i32) -> bool {
+        let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) else {
+            return false;
+        };
+        let Some(action) = win.pdf_viewer_action_at(mouse_x, mouse_y) else {
+            return false;
+        };
+        win.pdf_viewer_apply(action);
+        true
+    }
+
+    fn handle_applet_click(&mut self, win_id: usize, mouse_x: i32, mouse_y: i32) -> bool {
+        let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) else {
+            return false;
+        };
+        if let Some(label) = win.calculator_key_at(mouse_x, mouse_y) {
+            win.calculator_press(label);
+            return true;
+        }
+        if let Some(action) = win.calendar_action_at(mouse_x, mouse_y) {
+            return win.calendar_apply(action);
+        }
+        if let Some(action) = win.clock_action_at(mouse_x, mouse_y) {
+            return win.clock_apply(action);
+        }
+        if let Some(action) = win.recorder_action_at(mouse_x, mouse_y) {
+            return win.recorder_apply(action);
+        }
+        false
+    }
+
+    fn handle_boot_entries_click(&mut self, win_id: usize, mouse_x: i32, mouse_y: i32) -> bool {
+        let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) else {
+            return false;
+        };
+        let Some(action) = win.boot_entries_action_at(mouse_x, mouse_y) else {
+            return false;
+        };
+
+        let selected = win.boot_entries_selected();
+        let plan = &mut win.boot_entries_plan;
+        let mut status: Option<String> = None;
+        match (action, selected) {
+            (BootEntriesClickAction::Select(idx), _) => {
+                win.boot_entries_list.select(Some(idx));
+                if let Some(cells) = win.boot_entries_list.rows().get(idx) {
+                    let line = alloc::format!("{} {} - {}", cells[1], cells[3], cells[4]);
+                    status = Some(Self::trim_ascii_line(line.as_str(), 72));
+                }
+            }
+            (BootEntriesClickAction::Up, Some(id)) => plan.shift(id, -1),
+            (BootEntriesClickAction::Down, Some(id)) => plan.shift(id, 1),
+            (BootEntriesClickAction::Toggle, Some(id)) => {
+                let active = plan.is_active(id);
+                plan.set_active(id, !active);
+            }
+            (BootEntriesClickAction::Next, Some(id)) => {
+                plan.next = if plan.next == Some(id) { None } else { Some(id) };
+            }
+            (BootEntriesClickAction::Delete, Some(id)) => plan.delete(id),
+            (BootEntriesClickAction::Clean, _) => {
+                let stale =
+                    crate::bootvar::find_stale(&win.boot_entries_state, &crate::bootvar::present_partitions());
+                plan.clean(&stale);
+                status = Some(if stale.is_empty() {
+                    String::from("No hay entradas duplicadas ni obsoletas.")
+                } else {
+                    alloc::format!("{} entradas marcadas para borrar; revisa y pulsa Aplicar.", stale.len())
+                });
+            }
+            (BootEntriesClickAction::Apply, _) => {
+                match crate::bootvar::apply(&win.boot_entries_state, &win.boot_entries_plan) {
+                    Ok(lines) if lines.is_empty() => status = Some(String::from("Sin cambios pendientes.")),
+                    Ok(lines) => {
+                        win.reload_boot_entries();
+                        status = Some(alloc::format!("Aplicado. {}", lines.last().map(String::as_str).unwrap_or("")));
+                    }
+                    Err(err) => status = Some(alloc::format!("Error: {}", err)),
+                }
+            }
+            (BootEntriesClickAction::Discard, _) | (BootEntriesClickAction::Reload, _) => {
+                win.reload_boot_entries();
+            }
+            (BootEntriesClickAction::Restore, _) => {
+                status = Some(match crate::bootvar::restore(false) {
+                    Ok(lines) if lines.is_empty() => String::from("La copia coincide con el estado actual."),
+                    Ok(lines) => alloc::format!("Restaurado desde {} ({} cambios)", crate::bootvar::BACKUP_PATH, lines.len()),
+                    Err(err) => alloc::format!("Error: {}", err),
+                });
+                win.reload_boot_entries();
+            }
+            (_, None) => status = Some(String::from("Selecciona una entrada.")),
+        }
+
+        if !matches!(action, BootEntriesClickAction::Select(_)) {
+            win.rebuild_boot_entries_list();
+        }
+        if let Some(text) = status {
+            win.boot_entries_status = text;
+        }
+        win.render();
+        true
+    }
+
+    fn handle_about_pc_wheel(&mut self, mouse_x: i32, mouse_y: i32, wheel_delta: i32) -> bool {
+        if wheel_delta == 0 {
+            return false;
+        }
+
+        let magnitude = wheel_delta.unsigned_abs() as usize;
+        let rows = if magnitude >= 120 { magnitude / 120 } else { magnitude.max(1) };
+        let delta_rows = if wheel_delta > 0 {
+            rows as i32
+        } else {
+            -(rows as i32)
+        };
+
+        for i in (0..self.windows.len()).rev() {
+            if !self.window_on_active_desktop(&self.windows[i]) {
+                continue;
+            }
+            if self.windows[i].state != WindowState::Normal
+                && self.windows[i].state != WindowState::Maximized
+            {
+                continue;
+            }
+            if !self.windows[i].rect.contains(Point { x: mouse_x, y: mouse_y }) {
+                continue;
+            }
+            if !self.windows[i].is_about_pc() {
+                continue;
+            }
+
+            let win_id = self.windows[i].id;
+            let changed = self.windows[i].about_pc_scroll_by(delta_rows);
+            if changed {
+                self.active_window_id = Some(win_id);
+            }
+            return changed;
+        }
+
+        false
+    }
+
+    fn handle_mail_click(&mut self, win_id: usize, mouse_x: i32, mouse_y: i32) -> bool {
+        let action = {
+            let Some(win) = self.windows.iter().find(|w| w.id == win_id) else {
+                return false;
+            };
+            if !win.is_mail() {
+                return false;
+            }
+            win.mail_action_at(mouse_x, mouse_y)
+        };
+        let Some(action) = action else {
+            return false;
+        };
+
+        match action {
+            MailClickAction::Field(idx) => {
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    win.mail_focus = idx;
+                    win.render();
+                }
+            }
+            MailClickAction::Compose | MailClickAction::Reply => {
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    let original = win.mail_selected.and_then(|i| win.mail_entries.get(i)).cloned();
+                    if action == MailClickAction::Reply {
+                        let Some(original) = original else {
+                            win.mail_status = String::from("Selecciona un mensaje para responder.");
+                            win.render();
+                            return true;
+                        };
+                        let subject = if original.subject.to_ascii_lowercase().starts_with("re:") {
+                            original.subject.clone()
+                        } else {
+                            alloc::format!("Re: {}", original.subject)
+                        };
+                        let mut body = alloc::format!("\n\n{} escribio:\n", original.from);
+                        for line in win.mail_body_lines.iter() {
+                            body.push_str("> ");
+                            body.push_str(line.as_str());
+                            body.push('\n');
+                        }
+                        win.mail_fields = alloc::vec![original.from.clone(), subject, body];
+                        win.mail_focus = 2;
+                    } else {
+                        win.mail_fields = alloc::vec![String::new(), String::new(), String::new()];
+                        win.mail_focus = 0;
+                    }
+                    win.mail_view = MailView::Compose;
+                    win.mail_status = String::from("Flechas arriba/abajo cambian de campo. Enter en el mensaje = nueva linea.");
+                    win.render();
+                }
+            }
+            MailClickAction::Account => {
+                let account = crate::net::mail::load_account();
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    win.mail_fields = match account {
+                        Some(a) => alloc::vec![
+                            a.email,
+                            a.password,
+                            alloc::format!("{}:{}", a.imap_host, a.imap_port),
+                            alloc::format!("{}:{}", a.smtp_host, a.smtp_port),
+                        ],
+                        None => alloc::vec![String::new(), String::new(), String::new(), String::new()],
+                    };
+                    win.mail_focus = 0;
+                    win.mail_view = MailView::Account;
+                    win.mail_status = crate::net::mail::status_line();
+                    win.render();
+                }
+            }
+            MailClickAction::Cancel => {
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    win.mail_view = MailView::Inbox;
+                    win.mail_status = String::from("Listo.");
+                    win.render();
+                }
+            }
+            MailClickAction::SaveAccount => {
+                let fields = self
+                    .windows
+                    .iter()
+                    .find(|w| w.id == win_id)
+                    .map(|w| w.mail_fields.clone())
+                    .unwrap_or_default();
+                let status = match Self::mail_account_from_fields(&fields) {
+                    Ok(account) => crate::net::mail::save_account(&account)
+                        .map(|_| crate::net::mail::status_line()),
+                    Err(e) => Err(e),
+                };
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    match status {
+                        Ok(line) => {
+                            win.mail_view = MailView::Inbox;
+                            win.mail_status = line;
+                        }
+                        Err(e) => win.mail_status = alloc::format!("Error: {}", e),
+                    }
+                    win.render();
+                }
+            }
+            MailClickAction::Refresh => {
+                self.set_mail_status(win_id, "Actualizando bandeja de entrada...");
+                let result = {
+                    let mut pump = || self.pump_ui_while_blocked_net();
+                    crate::net::mail::refresh_inbox(crate::net::mail::MAIL_INBOX_DEFAULT_COUNT, &mut pump)
+                };
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    match result {
+                        Ok(total) => {
+                            win.mail_entries = crate::net::mail::cached_inbox();
+                            win.mail_selected = None;
+                            win.mail_body_lines.clear();
+                            win.mail_list_scroll = 0;
+                            win.mail_status = alloc::format!(
+                                "INBOX: {} mensajes, {} sin leer entre los ultimos {}.",
+                                total,
+                                win.mail_entries.iter().filter(|m| !m.seen).count(),
+                                win.mail_entries.len()
+                            );
+                        }
+                        Err(e) => win.mail_status = alloc::format!("Error: {}", e),
+                    }
+                    win.render();
+                }
+            }
+            MailClickAction::Select(idx) => {
+                let uid = self
+                    .windows
+                    .iter()
+                    .find(|w| w.id == win_id)
+                    .and_then(|w| w.mail_entries.get(idx))
+                    .map(|m| m.uid);
+                let Some(uid) = uid else {
+                    return true;
+                };
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    win.mail_selected = Some(idx);
+                    win.mail_body_lines.clear();
+                    win.mail_body_scroll = 0;
+                    win.mail_status = String::from("Descargando mensaje...");
+                    win.render();
+                }
+                self.paint();
+                let result = {
+                    let mut pump = || self.pump_ui_while_blocked_net();
+                    crate::net::mail::fetch_message(uid, &mut pump)
+                };
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    match result {
+                        Ok(message) => {
+                            win.mail_body_lines = message.text.lines().map(String::from).collect();
+                            if let Some(entry) = win.mail_entries.get_mut(idx) {
+                                entry.seen = true;
+                            }
+                            win.mail_status = alloc::format!("Para: {}", message.to);
+                        }
+                        Err(e) => win.mail_status = alloc::format!("Error: {}", e),
+                    }
+                    win.render();
+                }
+            }
+            MailClickAction::Send => {
+                let fields = self
+                    .windows
+                    .iter()
+                    .find(|w| w.id == win_id)
+                    .map(|w| w.mail_fields.clone())
+                    .unwrap_or_default();
+                if fields.len() < 3 {
+                    return true;
+                }
+                self.set_mail_status(win_id, "Enviando...");
+                let result = {
+                    let mut pump = || self.pump_ui_while_blocked_net();
+                    crate::net::mail::send_message(fields[0].as_str(), fields[1].as_str(), fields[2].as_str(), &mut pump)
+                };
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    match result {
+                        Ok(()) => {
+                            win.mail_view = MailView::Inbox;
+                            win.mail_status = alloc::format!("Mensaje enviado a {}.", fields[0]);
+                        }
+                        Err(e) => win.mail_status = alloc::format!("Error: {}", e),
+                    }
+                    win.render();
+                }
+            }
+        }
+        true
+    }
+
+    fn set_mail_status(&mut self, win_id: usize, text: &str) {
+        if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+            win.mail_status = String::from(text);
+            win.render();
+        }
+        // The network pump only repaints when the mouse moves.
+        self.paint();
+    }
+
+    /// Account from the Mail window form: email, password, IMAP and SMTP as
+    /// `host` or `host:puerto`. Empty servers keep the `imap.`/`smtp.`
+    /// guesses; a standard port also picks its security mode.
+    fn mail_account_from_fields(fields: &[String]) -> Result<crate::net::mail::MailAccount, String> {
+        let field = |i: usize| fields.get(i).map(|f| f.trim()).unwrap_or("");
+        let mut account = match crate::net::mail::load_account() {
+            Some(mut a) if a.email.eq_ignore_ascii_case(field(0)) => {
+                a.password = String::from(field(1));
+                a
+            }
+            _ => crate::net::mail::MailAccount::for_address(field(0), field(1))
+                .ok_or_else(|| String::from("direccion invalida"))?,
+        };
+        for (idx, prefix) in [(2usize, "imap"), (3usize, "smtp")] {
+            let value = field(idx);
+            if value.is_empty() {
+                continue;
+            }
+            let (host, port) = match value.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (value, None),
+            };
+            account.set(alloc::format!("{}_host", prefix).as_str(), host)?;
+            if let Some(port) = port {
+                let security = match port {
+                    "993" | "465" => Some("tls"),
+                    "143" | "587" => Some("starttls"),
+                    _ => None,
+                };
+                if let Some(security) = security {
+                    account.set(alloc::format!("{}_security", prefix).as_str(), security)?;
+                }
+                account.set(alloc::format!("{}_port", prefix).as_str(), port)?;
+            }
+        }
+        Ok(account)
+    }
+
+    fn handle_mail_wheel(&mut self, mouse_x: i32, mouse_y: i32, wheel_delta: i32) -> bool {
+        if wheel_delta == 0 {
+            return false;
+        }
+
+        let magnitude = wheel_delta.unsigned_abs() as usize;
+        let rows = if magnitude >= 120 { magnitude / 120 } else { magnitude.max(1) };
+        let delta_rows = if wheel_delta > 0 {
+            rows as i32
+        } else {
+            -(rows as i32)
+        };
+
+        for i in (0..self.windows.len()).rev() {
+            if !self.window_on_active_desktop(&self.windows[i]) {
+                continue;
+            }
+            if self.windows[i].state != WindowState::Normal
+                && self.windows[i].state != WindowState::Maximized
+            {
+                continue;
+            }
+            if !self.windows[i].rect.contains(Point { x: mouse_x, y: mouse_y }) {
+                continue;
+            }
+            if !self.windows[i].is_mail() {
+                continue;
+            }
+
+            let win_id = self.windows[i].id;
+            let changed = self.windows[i].mail_scroll_at(mouse_x, delta_rows);
+            if changed {
+                self.active_window_id = Some(win_id);
+            }
+            return changed;
+        }
+
+        false
+    }
+
+    fn handle_notepad_wheel(&mut self, mouse_x: i32, mouse_y: i32, wheel_delta: i32) -> bool {
+        if wheel_delta == 0 {
+            return false;
+        }
+        let delta_rows = if wheel_delta > 0 { 1 } else { -1 };
+        for i in (0..self.windows.len()).rev() {
+            if !self.window_on_active_desktop(&self.windows[i]) {
+                continue;
+            }
+            if self.windows[i].state != WindowState::Normal
+                && self.windows[i].state != WindowState::Maximized
+            {
+                continue;
+            }
+            if !self.windows[i].rect.contains(Point { x: mouse_x, y: mouse_y }) {
+                continue;
+            }
+            if !self.windows[i].is_notepad() {
+                return false;
+            }
+            return self.windows[i].notepad_scroll_by(delta_rows);
+        }
+        false
+    }
+
     fn handle_doom_launcher_click(&mut self, win_id: usize, mouse_x: i32, mouse_y: i32) {
         let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) else {
             return;
@@ -36392,6 +40404,38 @@ impl Compositor {
                     self.open_task_manager_window();
                     true
                 }
+                "aboutpc" => {
+                    self.open_about_pc_window();
+                    true
+                }
+                "bootvar" => {
+                    self.open_boot_entries_window();
+                    true
+                }
+                "pdf" => {
+                    self.open_pdf_viewer_window();
+                    true
+                }
+                "calc" | "calculadora" => {
+                    self.open_calculator_window();
+                    true
+                }
+                "calendar" | "calendario" => {
+                    self.open_calendar_window();
+                    true
+                }
+                "clock" | "reloj" => {
+                    self.open_clock_window();
+                    true
+                }
+                "recorder" | "grabadora" => {
+                    self.open_recorder_window();
+                    true
+                }
+                "mail" => {
+                    self.open_mail_window();
+                    true
+                }
                 _ => false,
             }
         } else if category == "folder" {
@@ -36486,6 +40530,8 @@ impl Compositor {
 
             if kind == "img" {
                 self.open_png_from_explorer_file(0, &item);
+            } else if kind == "pdf" {
+                self.open_pdf_from_explorer_file(0, &item);
             } else if kind == "aud" {
                 self.open_media_player_file(item.cluster, item.label.as_str(), item.size);
             } else {
@@ -36503,6 +40549,139 @@ impl Compositor {
         }
     }
 
+    /// Windows that can be reopened, bottom of the stack first.
+    fn session_snapshot(&self) -> Vec<SavedWindow> {
+        let mut out = Vec::new();
+        for win in self.windows.iter() {
+            if win.state == WindowState::Closed {
+                continue;
+            }
+            let Some(binding) = self.window_recent_bindings.iter().find(|entry| entry.win_id == win.id) else {
+                continue;
+            };
+            let url = if win.kind == WindowKind::Browser && !win.browser_url.starts_with("redux://welcome") {
+                win.browser_url.clone()
+            } else {
+                String::new()
+            };
+            out.push(SavedWindow {
+                desktop_id: win.desktop_id.max(1),
+                state: match win.state {
+                    WindowState::Minimized => SessionState::Minimized,
+                    WindowState::Maximized => SessionState::Maximized,
+                    _ => SessionState::Normal,
+                },
+                rect: if win.state == WindowState::Maximized { win.saved_rect } else { win.rect },
+                url,
+                command: binding.command.clone(),
+            });
+        }
+        out
+    }
+
+    /// Called on the way to shutdown or restart.
+    fn save_session(&mut self) -> Result<usize, &'static str> {
+        if crate::gui::session::mode() == SessionMode::Never {
+            return crate::gui::session::clear().map(|_| 0);
+        }
+        let windows = self.session_snapshot();
+        crate::gui::session::save(windows.as_slice())
+    }
+
+    /// Reopen the saved session now, ask first or drop it, per `session.restore`.
+    pub fn offer_session_restore(&mut self) {
+        let windows = crate::gui::session::load();
+        if windows.is_empty() {
+            return;
+        }
+        match crate::gui::session::mode() {
+            SessionMode::Never => {
+                let _ = crate::gui::session::clear();
+            }
+            SessionMode::Always => {
+                let _ = self.restore_session(windows);
+            }
+            SessionMode::Ask => {
+                let text = alloc::format!(
+                    "Habia {} ventanas abiertas al apagar. Quieres volver a abrirlas?",
+                    windows.len()
+                );
+                self.open_dialog(
+                    Dialog::confirm("Restaurar sesion", text.as_str(), "Restaurar"),
+                    DialogPurpose::RestoreSession(windows),
+                );
+            }
+        }
+    }
+
+    /// Relaunch every saved window and put it back where it was. The saved
+    /// session is dropped afterwards so a crash doesn't offer it twice.
+    fn restore_session(&mut self, windows: Vec<SavedWindow>) -> usize {
+        let taskbar_top = self.taskbar.rect.y.max(1);
+        let mut restored = 0;
+        for saved in windows.iter() {
+            let first_new_id = self.next_id;
+            if !self.launch_recent_shortcut_command(saved.command.as_str()) {
+                continue;
+            }
+            let Some(win_id) = self.windows.iter().rev().find(|w| w.id >= first_new_id).map(|w| w.id) else {
+                continue;
+            };
+            restored += 1;
+
+            let (screen_w, screen_h) = (self.width, self.height);
+            let width = saved.rect.width.min(screen_w as u32);
+            let height = saved.rect.height.min(taskbar_top as u32);
+            let x = saved.rect.x.clamp(0, (screen_w as i32 - width as i32).max(0));
+            let y = saved.rect.y.clamp(0, (taskbar_top - height as i32).max(0));
+            let mut browse = None;
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                win.move_to(x, y);
+                win.resize_to(width, height);
+                if saved.state == SessionState::Maximized {
+                    win.maximize(screen_w, screen_h);
+                }
+                if win.kind == WindowKind::Browser && !saved.url.is_empty() {
+                    browse = Some(saved.url.clone());
+                }
+            }
+            if let Some(url) = browse {
+                self.browser_navigate_to(win_id, url.as_str());
+            }
+            let index = saved.desktop_id.saturating_sub(1) as usize;
+            if index != self.active_desktop_index && self.ensure_virtual_desktop(index) {
+                let _ = self.move_window_to_desktop(win_id, index);
+            }
+            if saved.state == SessionState::Minimized {
+                self.minimize_window(win_id);
+            }
+        }
+        let _ = crate::gui::session::clear();
+        self.sync_active_window_for_desktop();
+        self.desktop_surface_status = alloc::format!("Sesion restaurada: {} de {} ventanas.", restored, windows.len());
+        self.needs_repaint = true;
+        restored
+    }
+
+    /// `session save|load` need the windows; the rest is `session::command_lines`.
+    fn session_command_lines(&mut self, args: &str) -> Vec<String> {
+        match args.trim() {
+            "save" => match self.save_session() {
+                Ok(saved) => alloc::vec![alloc::format!("Sesion guardada: {} ventanas.", saved)],
+                Err(e) => alloc::vec![alloc::format!("session: {}", e)],
+            },
+            "load" => {
+                let windows = crate::gui::session::load();
+                if windows.is_empty() {
+                    return alloc::vec![String::from("session: no hay ninguna sesion guardada")];
+                }
+                let restored = self.restore_session(windows);
+                alloc::vec![alloc::format!("Sesion restaurada: {} ventanas.", restored)]
+            }
+            other => crate::gui::session::command_lines(other),
+        }
+    }
+
     fn launch_start_app_shortcut(&mut self, shortcut: &StartAppShortcut) {
         let command = shortcut.command.trim();
         if command.is_empty() {
@@ -36537,6 +40716,26 @@ impl Compositor {
             self.open_task_manager_window();
             return;
         }
+        if verb == "mail" {
+            self.open_mail_window();
+            return;
+        }
+        if verb == "calc" {
+            self.open_calculator_window();
+            return;
+        }
+        if verb == "calendar" {
+            self.open_calendar_window();
+            return;
+        }
+        if verb == "clock" {
+            self.open_clock_window();
+            return;
+        }
+        if verb == "recorder" {
+            self.open_recorder_window();
+            return;
+        }
         if verb == "shell" {
             let launch_result = crate::launch_uefi_shell();
             let _ = crate::restore_gui_after_external_app();
@@ -36559,7 +40758,7 @@ impl Compositor {
         // ---------- runapp: open .RML in App Runner ----------
         if verb == "runapp" && !arg.is_empty() {
             let mut out = Vec::new();
-            let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
             if fat.bytes_per_sector == 0 {
                 if self.manual_unmount_lock {
                     out.push(String::from(
@@ -36633,18 +40832,31 @@ impl Compositor {
 
     fn execute_command(&mut self, win_id: usize, cmd: &str) {
         use crate::fs::FileType;
-        let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+        let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
 
         let trimmed = cmd.trim();
         if trimmed.is_empty() {
             return;
         }
+        let _acting = crate::perm::act_as(crate::perm::session());
 
         let mut parts = trimmed.splitn(2, ' ');
         let verb_raw = parts.next().unwrap_or("");
         let arg_raw = parts.next().unwrap_or("").trim();
         let verb = Self::ascii_lower(verb_raw);
-        let is_fs_cmd = verb == "ls" || verb == "cd" || verb == "cat" || verb == "cp" || verb == "mv" || verb == "notepad";
+        let is_fs_cmd = verb == "ls"
+            || verb == "cd"
+            || verb == "cat"
+            || verb == "cp"
+            || verb == "mv"
+            || verb == "notepad"
+            || verb == "ln"
+            || verb == "readlink"
+            || verb == "xattr"
+            || verb == "chmod"
+            || verb == "chown"
+            || verb == "stat"
+            || verb == "chkdsk";
 
         if verb == "wry" {
             if !WEB_CEF_BRIDGE_ENABLED {
@@ -36718,9 +40930,781 @@ impl Compositor {
             return;
         }
 
+        if verb == "lspci" {
+            let out = crate::pci::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "selftest" {
+            let out = crate::selftest::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "firewall" {
+            let out = crate::net::firewall::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "gemini" {
+            let out = crate::net::gemini::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "mail" {
+            if arg_raw.trim().eq_ignore_ascii_case("gui") {
+                self.open_mail_window();
+                return;
+            }
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+            let dir_cluster = self.terminal_current_cluster(win_id, fat);
+            let out = {
+                let mut pump = || self.pump_ui_while_blocked_net();
+                crate::net::mail::command_lines(arg_raw, dir_cluster, &mut pump)
+            };
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "ftp" {
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+            let dir_cluster = self.terminal_current_cluster(win_id, fat);
+            let out = {
+                let mut pump = || self.pump_ui_while_blocked_net();
+                crate::net::ftp::command_lines(arg_raw, dir_cluster, &mut pump)
+            };
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "config" {
+            let out = crate::config::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "gamepad" {
+            let out = crate::gamepad::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "boottime" {
+            let out = crate::boottime::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "trace" {
+            let out = crate::trace::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "perf" {
+            let out = crate::perf::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            self.needs_repaint = true;
+            return;
+        }
+
+        if verb == "screenshot" {
+            let line = self.begin_screenshot();
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                win.add_output(line.as_str());
+                win.render_terminal();
+            }
+            self.needs_repaint = true;
+            return;
+        }
+
+        if verb == "scale" {
+            let out = crate::gui::hidpi::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "effects" {
+            let out = match arg_raw.trim().strip_prefix("opacity") {
+                Some(rest) => self.set_window_opacity_lines(win_id, rest),
+                None => crate::gui::effects::command_lines(arg_raw),
+            };
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            self.needs_repaint = true;
+            return;
+        }
+
+        if verb == "session" {
+            let out = self.session_command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            self.needs_repaint = true;
+            return;
+        }
+
+        if verb == "workspace" {
+            let out = self.workspace_command_lines(win_id, arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            self.needs_repaint = true;
+            return;
+        }
+
+        if verb == "lang" {
+            let out = crate::i18n::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            self.needs_repaint = true;
+            return;
+        }
+
+        if verb == "notify" {
+            let out = crate::gui::notifications::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "pkg" {
+            let out = {
+                let mut pump = || self.pump_ui_while_blocked_net();
+                crate::pkg::command_lines(arg_raw, &mut pump)
+            };
+            // Post-install hooks may have added or removed launchers.
+            self.refresh_start_app_shortcuts();
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "gzip" || verb == "gunzip" || verb == "tar" || verb == "unzip" || verb == "zip" {
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+            let dir_cluster = self.terminal_current_cluster(win_id, fat);
+            let out = if verb == "gzip" || verb == "gunzip" {
+                crate::compress::command_lines(verb.as_str(), arg_raw, dir_cluster)
+            } else {
+                crate::archive::command_lines(verb.as_str(), arg_raw, dir_cluster)
+            };
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "host" {
+            let out = {
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+                let dir_cluster = self.terminal_current_cluster(win_id, fat);
+                crate::hostfs::command_lines(arg_raw, dir_cluster)
+            };
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        // Mount table and paths below other mounts (/host, /sys, /dev, /data...).
+        if let Some(out) = crate::fs::vfs::command_lines(verb.as_str(), arg_raw) {
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "hwinfo" || verb == "about" {
+            let args = if verb == "about" { "gui" } else { arg_raw.trim() };
+            if args.eq_ignore_ascii_case("gui") || args.eq_ignore_ascii_case("pc") {
+                self.open_about_pc_window();
+                return;
+            }
+            let out = crate::hwinfo::command_lines(args);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if let Some(args) = crate::bench::command_args(trimmed) {
+            let out = {
+                let mut hook = |hook: crate::bench::Hook| match hook {
+                    crate::bench::Hook::PumpUi => self.pump_ui_while_blocked_net(),
+                    crate::bench::Hook::Redraw => self.paint(),
+                };
+                crate::bench::command_lines(args.as_str(), Some(&mut hook))
+            };
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "cryptobench" {
+            let out = crate::crypto::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "pdf" {
+            let args = arg_raw.trim();
+            let (sub, target) = args.split_once(' ').unwrap_or((args, ""));
+            let target = target.trim();
+            if sub.eq_ignore_ascii_case("open") && !target.is_empty() {
+                let (target, page) = match target.split_once('#') {
+                    Some((url, fragment)) => (url, crate::pdf::page_fragment(fragment)),
+                    None => (target, 0),
+                };
+                let loaded = if target.starts_with("http://") || target.starts_with("https://") {
+                    let mut pump = || self.pump_ui_while_blocked_net();
+                    crate::web_engine::fetch_pdf(target, &mut pump)
+                        .and_then(|data| crate::pdf::Document::parse(data).map_err(String::from))
+                } else {
+                    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+                    let dir_cluster = self.terminal_current_cluster(win_id, fat);
+                    crate::pdf::open_file(dir_cluster, target)
+                };
+                let line = match loaded {
+                    Ok(document) => {
+                        let name = target.rsplit('/').find(|part| !part.is_empty()).unwrap_or(target);
+                        let pages = document.page_count();
+                        self.show_pdf_document(name, document, page);
+                        alloc::format!("pdf: {} abierto ({} paginas)", name, pages)
+                    }
+                    Err(err) => alloc::format!("pdf: {}", err),
+                };
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    win.add_output(line.as_str());
+                    win.render_terminal();
+                }
+                return;
+            }
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+            let dir_cluster = self.terminal_current_cluster(win_id, fat);
+            let out = crate::pdf::command_lines(arg_raw, dir_cluster);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "calc" {
+            if arg_raw.trim().is_empty() {
+                self.open_calculator_window();
+                return;
+            }
+            let out = crate::calc::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "cal" {
+            if arg_raw.trim().eq_ignore_ascii_case("gui") {
+                self.open_calendar_window();
+                return;
+            }
+            let out = crate::calendar::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            for win in self.windows.iter_mut().filter(|w| w.is_calendar()) {
+                win.calendar_reload();
+                win.render();
+            }
+            return;
+        }
+
+        if verb == "mic" {
+            if arg_raw.trim().eq_ignore_ascii_case("gui") {
+                self.open_recorder_window();
+                return;
+            }
+            let out = crate::capture::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            for win in self.windows.iter_mut().filter(|w| w.is_recorder()) {
+                win.render();
+            }
+            self.needs_repaint = true;
+            return;
+        }
+
+        if verb == "remote" {
+            let out = crate::net::remote::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            self.needs_repaint = true;
+            return;
+        }
+
+        if verb == "cmdline" {
+            let out = crate::cmdline::command_lines();
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            self.needs_repaint = true;
+            return;
+        }
+
+        if verb == "power" {
+            let out = crate::pm::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            self.needs_repaint = true;
+            return;
+        }
+
+        if verb == "thunderbolt" {
+            let out = crate::thunderbolt::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            self.needs_repaint = true;
+            return;
+        }
+
+        if verb == "usb" {
+            let out = crate::usb::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            self.needs_repaint = true;
+            return;
+        }
+
+        if verb == "sound" {
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+            let dir_cluster = self.terminal_current_cluster(win_id, fat);
+            let out = crate::synth::command_lines(arg_raw, dir_cluster);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            self.needs_repaint = true;
+            return;
+        }
+
+        if verb == "alarm" {
+            if arg_raw.trim().eq_ignore_ascii_case("gui") {
+                self.open_clock_window();
+                return;
+            }
+            let out = crate::alarm::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "index" {
+            let out = crate::search_index::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "heapcheck" {
+            let out = crate::heapcheck::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "fswatch" {
+            let out = crate::fswatch::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "su" || verb == "id" || verb == "whoami" {
+            let out = crate::perm::session_lines(verb.as_str(), arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "bootvar" {
+            if arg_raw.trim().eq_ignore_ascii_case("gui") {
+                self.open_boot_entries_window();
+                return;
+            }
+            let out = crate::bootvar::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "osprober" {
+            let out = crate::osprober::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "async" {
+            let out = crate::executor::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "jobs" {
+            let out = crate::procenv::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "touch" {
+            let out = crate::touch::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+        if verb == "gamma" {
+            let out = crate::gamma::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+        if verb == "print" {
+            let out = {
+                let mut pump = || self.pump_ui_while_blocked_net();
+                crate::print::command_lines(arg_raw, &mut pump)
+            };
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+        if verb == "osk" {
+            let out = crate::gui::osk::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "clocksource" {
+            let out = crate::clocksource::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "devices" {
+            let out = crate::device::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "mod" {
+            let out = crate::kmod::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "checkpoint" {
+            let out = crate::checkpoint::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "replay" {
+            let out = crate::replay::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "linuxboot" {
+            // `boot` hands the screen to the kernel; take it back if it returns.
+            let booting = arg_raw.trim_start().starts_with("boot");
+            let out = crate::linuxboot::command_lines(arg_raw);
+            if booting {
+                let _ = crate::restore_gui_after_external_app();
+            }
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "crypt" {
+            let out = crate::crypt::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "tpm" {
+            let out = crate::tpm::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "secureboot" {
+            let out = crate::secureboot::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "random" {
+            let out = crate::random::command_lines(arg_raw);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
+        if verb == "log" || verb == "dmesg" {
+            let args = if verb == "dmesg" { "" } else { arg_raw };
+            let out = crate::klog::command_lines(args);
+            if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                for line in out.iter() {
+                    win.add_output(line.as_str());
+                }
+                win.render_terminal();
+            }
+            return;
+        }
+
         if verb == "suspend" || verb == "sleep" {
             if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
-                win.add_output("Attempting ACPI S3 suspend...");
+                win.add_output("Suspendiendo...");
                 win.render_terminal();
             }
             self.enter_suspend();
@@ -38165,91 +43149,90 @@ impl Compositor {
                         out.push(alloc::format!("WebKit endpoint activo: {}", self.web_proxy_base()));
                     }
                 } else if cmd == "ping" {
-                    let (base, raw, tried_bases) = self.web_cef_request_first_reachable("status");
-                    match raw {
-                        Some(raw) => {
-                            let (code, body) = Self::parse_http_status_and_body(raw.as_str());
-                            out.push(alloc::format!("WebKit ping: HTTP {:?}", code));
-                            if let Some(base) = base {
-                                out.push(alloc::format!("Endpoint: {}", base));
-                            }
-                            for line in body.lines().take(10) {
-                                out.push(String::from(line.trim_end()));
-                            }
-                        }
-                        None => {
+                    let candidates = self.web_proxy_candidate_bases();
+                    self.terminal_spawn_net_job(win_id, &mut out, async move {
+                        let mut out = Vec::new();
+                        let Some((base, raw)) =
+                            Self::web_cef_request_first_reachable(candidates.as_slice(), "status").await
+                        else {
                             out.push(String::from(
                                 "WebKit ping fallo: no se pudo conectar al endpoint.",
                             ));
                             out.push(String::from("Endpoints intentados:"));
-                            for base in tried_bases.iter().take(4) {
+                            for base in candidates.iter().take(4) {
                                 out.push(alloc::format!("  - {}", base));
                             }
+                            return (out, None);
+                        };
+                        let (code, body) = Self::parse_http_status_and_body(raw.as_str());
+                        out.push(alloc::format!("WebKit ping: HTTP {:?}", code));
+                        out.push(alloc::format!("Endpoint: {}", base));
+                        for line in body.lines().take(10) {
+                            out.push(String::from(line.trim_end()));
                         }
-                    }
+                        (out, Some(base))
+                    });
                 } else if cmd == "open" {
                     let url = parts.next().unwrap_or("").trim();
                     let extra = parts.next();
                     if url.is_empty() || extra.is_some() {
                         out.push(String::from("Usage: web webkit open <url>"));
                     } else {
-                        let (base, raw, tried_bases) = self.web_cef_request_first_reachable(
-                            alloc::format!("open?url={}", Self::url_encode_component(url)).as_str(),
-                        );
-                        match raw {
-                            Some(raw) => {
-                                let (code, body) = Self::parse_http_status_and_body(raw.as_str());
-                                out.push(alloc::format!("WebKit open: HTTP {:?}", code));
-                                if let Some(base) = base {
-                                    out.push(alloc::format!("Endpoint: {}", base));
-                                    if let Some(surface) =
-                                        self.browser_fetch_cef_frame_with_base(base.as_str())
-                                    {
-                                        out.push(alloc::format!(
-                                            "Frame: {}x{} ({})",
-                                            surface.width, surface.height, surface.source
-                                        ));
-                                    } else {
-                                        out.push(String::from(
-                                            "Frame: no disponible (host sin /frame o timeout).",
-                                        ));
-                                    }
-                                }
-                                for line in body.lines().take(10) {
-                                    out.push(String::from(line.trim_end()));
-                                }
-                            }
-                            None => {
+                        let candidates = self.web_proxy_candidate_bases();
+                        let query = alloc::format!("open?url={}", Self::url_encode_component(url));
+                        self.terminal_spawn_net_job(win_id, &mut out, async move {
+                            let mut out = Vec::new();
+                            let Some((base, raw)) =
+                                Self::web_cef_request_first_reachable(candidates.as_slice(), query.as_str()).await
+                            else {
                                 out.push(String::from(
                                     "WebKit open fallo: no se pudo conectar al endpoint.",
                                 ));
                                 out.push(String::from("Endpoints intentados:"));
-                                for base in tried_bases.iter().take(4) {
+                                for base in candidates.iter().take(4) {
                                     out.push(alloc::format!("  - {}", base));
                                 }
+                                return (out, None);
+                            };
+                            let (code, body) = Self::parse_http_status_and_body(raw.as_str());
+                            out.push(alloc::format!("WebKit open: HTTP {:?}", code));
+                            out.push(alloc::format!("Endpoint: {}", base));
+                            if let Some(surface) = Self::browser_fetch_cef_frame_with_base(base.as_str()).await {
+                                out.push(alloc::format!(
+                                    "Frame: {}x{} ({})",
+                                    surface.width, surface.height, surface.source
+                                ));
+                            } else {
+                                out.push(String::from(
+                                    "Frame: no disponible (host sin /frame o timeout).",
+                                ));
                             }
-                        }
+                            for line in body.lines().take(10) {
+                                out.push(String::from(line.trim_end()));
+                            }
+                            (out, Some(base))
+                        });
                     }
                 } else if cmd == "frame" {
-                    let mut frame_found = false;
-                    for base in self.web_proxy_candidate_bases().into_iter() {
-                        if let Some(surface) = self.browser_fetch_cef_frame_with_base(base.as_str()) {
-                            self.web_proxy_endpoint_base = base.clone();
-                            out.push(String::from("WebKit frame: OK"));
-                            out.push(alloc::format!("Endpoint: {}", base));
-                            out.push(alloc::format!(
-                                "Frame: {}x{} ({})",
-                                surface.width, surface.height, surface.source
-                            ));
-                            frame_found = true;
-                            break;
+                    let candidates = self.web_proxy_candidate_bases();
+                    self.terminal_spawn_net_job(win_id, &mut out, async move {
+                        let mut out = Vec::new();
+                        for base in candidates.into_iter() {
+                            if let Some(surface) = Self::browser_fetch_cef_frame_with_base(base.as_str()).await {
+                                out.push(String::from("WebKit frame: OK"));
+                                out.push(alloc::format!("Endpoint: {}", base));
+                                out.push(alloc::format!(
+                                    "Frame: {}x{} ({})",
+                                    surface.width, surface.height, surface.source
+                                ));
+                                return (out, Some(base));
+                            }
                         }
-                    }
-                    if !frame_found {
                         out.push(String::from(
                             "WebKit frame: no disponible (host sin /frame o timeout).",
                         ));
-                    }
+                        (out, None)
+                    });
                 } else if cmd == "input" {
                     let kind = Self::ascii_lower(parts.next().unwrap_or(""));
                     let mut query = String::new();
@@ -38288,30 +43271,30 @@ impl Compositor {
                             "Usage: web webkit input <click x y|scroll d|key K|text T|back|forward|reload>",
                         ));
                     } else {
-                        let (base, raw, tried_bases) =
-                            self.web_cef_request_first_reachable(query.as_str());
-                        match raw {
-                            Some(raw) => {
-                                let (code, body) = Self::parse_http_status_and_body(raw.as_str());
-                                out.push(alloc::format!("WebKit input: HTTP {:?}", code));
-                                if let Some(base) = base {
-                                    out.push(alloc::format!("Endpoint: {}", base));
-                                }
-                                if !body.trim().is_empty() {
-                                    for line in body.lines().take(6) {
-                                        out.push(String::from(line.trim_end()));
-                                    }
-                                }
-                            }
-                            None => {
+                        let candidates = self.web_proxy_candidate_bases();
+                        self.terminal_spawn_net_job(win_id, &mut out, async move {
+                            let mut out = Vec::new();
+                            let Some((base, raw)) =
+                                Self::web_cef_request_first_reachable(candidates.as_slice(), query.as_str()).await
+                            else {
                                 out.push(String::from(
                                     "WebKit input fallo: no se pudo conectar al endpoint.",
                                 ));
-                                for base in tried_bases.iter().take(4) {
+                                for base in candidates.iter().take(4) {
                                     out.push(alloc::format!("  - {}", base));
                                 }
+                                return (out, None);
+                            };
+                            let (code, body) = Self::parse_http_status_and_body(raw.as_str());
+                            out.push(alloc::format!("WebKit input: HTTP {:?}", code));
+                            out.push(alloc::format!("Endpoint: {}", base));
+                            if !body.trim().is_empty() {
+                                for line in body.lines().take(6) {
+                                    out.push(String::from(line.trim_end()));
+                                }
                             }
-                        }
+                            (out, Some(base))
+                        });
                     }
                 } else {
                     out.push(String::from(
@@ -38719,10 +43702,92 @@ impl Compositor {
         }
 
         if verb == "net" {
+            let sub = arg_raw.trim();
+            let sub_lower = Self::ascii_lower(sub);
+            if sub_lower == "bench" || sub_lower.starts_with("bench ") {
+                let out = {
+                    let mut pump = || self.pump_ui_while_blocked_net();
+                    crate::net::bench::command_lines(sub[5..].trim(), &mut pump)
+                };
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    for line in out.iter() {
+                        win.add_output(line.as_str());
+                    }
+                    win.render_terminal();
+                }
+                return;
+            }
+            if sub_lower == "pool" || sub_lower.starts_with("pool ") {
+                let out = crate::net::pool::command_lines(sub[4..].trim());
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    for line in out.iter() {
+                        win.add_output(line.as_str());
+                    }
+                    win.render_terminal();
+                }
+                return;
+            }
+            if sub_lower == "profile" || sub_lower.starts_with("profile ") {
+                let out = crate::net::profile::command_lines(sub[7..].trim());
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    for line in out.iter() {
+                        win.add_output(line.as_str());
+                    }
+                    win.render_terminal();
+                }
+                return;
+            }
+            if sub_lower == "portal" || sub_lower.starts_with("portal ") {
+                let out = crate::net::portal::command_lines(sub[6..].trim());
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    for line in out.iter() {
+                        win.add_output(line.as_str());
+                    }
+                    win.render_terminal();
+                }
+                return;
+            }
+            if sub_lower == "stats" || sub_lower.starts_with("stats ") {
+                let out = crate::net::stats::command_lines(sub[5..].trim());
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    for line in out.iter() {
+                        win.add_output(line.as_str());
+                    }
+                    win.render_terminal();
+                }
+                return;
+            }
+            if sub_lower == "arp" || sub_lower.starts_with("arp ") {
+                let out = crate::net::arp::command_lines(sub[3..].trim());
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    for line in out.iter() {
+                        win.add_output(line.as_str());
+                    }
+                    win.render_terminal();
+                }
+                return;
+            }
+            if sub_lower == "mtu" || sub_lower.starts_with("mtu ") {
+                let out = crate::net::mtu::command_lines(sub[3..].trim());
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    for line in out.iter() {
+                        win.add_output(line.as_str());
+                    }
+                    win.render_terminal();
+                }
+                return;
+            }
+            if sub_lower == "jobs" || sub_lower.starts_with("jobs ") {
+                let out = crate::net::worker::command_lines(sub[4..].trim());
+                if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
+                    for line in out.iter() {
+                        win.add_output(line.as_str());
+                    }
+                    win.render_terminal();
+                }
+                return;
+            }
             if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
-                let sub = arg_raw.trim();
-                let sub_lower = Self::ascii_lower(sub);
-
                 if sub_lower == "dhcp" {
                     win.add_output(alloc::format!("Net: {}", crate::net::set_dhcp_mode()).as_str());
                     win.render_terminal();
@@ -38827,24 +43892,36 @@ impl Compositor {
                         );
                     } else {
                         win.add_output("NetDiag: Intel Ethernet no inicializado.");
+                        if crate::virtio::net::is_present() {
+                            win.add_output(crate::virtio::net::status_line().as_str());
+                        }
                     }
                     win.render_terminal();
                     return;
                 }
 
                 if !sub.is_empty() && sub_lower != "mode" {
-                    win.add_output("Usage: net [dhcp|static|static <ip> <prefijo> <gateway>|mode|https|diag]");
+                    win.add_output("Usage: net [dhcp|static|static <ip> <prefijo> <gateway>|mode|https|diag|bench|pool|jobs|profile|portal|stats|arp|mtu]");
                     win.render_terminal();
                     return;
                 }
 
-                let dhcp_status = unsafe { crate::net::DHCP_STATUS };
+                let dhcp_status = crate::net::dhcp_status();
                 let (s_ip, s_prefix, s_gw) = crate::net::get_static_ipv4_config();
                 win.add_output(alloc::format!("Net: transporte activo -> {}", crate::net::get_active_transport()).as_str());
                 win.add_output(alloc::format!("Net: failover policy -> {}", crate::net::get_failover_policy()).as_str());
                 win.add_output(alloc::format!("Net: modo IP -> {}", crate::net::get_network_mode()).as_str());
                 win.add_output(alloc::format!("Net: HTTPS mode -> {}", crate::net::get_https_mode()).as_str());
                 win.add_output(alloc::format!("Net: estado IP -> {}", dhcp_status).as_str());
+                for line in crate::net::dhcp::status_lines() {
+                    win.add_output(line.as_str());
+                }
+                for line in crate::net::profile::status_lines() {
+                    win.add_output(line.as_str());
+                }
+                win.add_output(
+                    alloc::format!("Net: portal -> {}", crate::net::portal::state().as_str()).as_str(),
+                );
                 win.add_output(
                     alloc::format!(
                         "Net: perfil fija -> {}.{}.{}.{}/{} gw {}.{}.{}.{}",
@@ -39081,94 +44158,20 @@ impl Compositor {
                             out.push(String::from("Fetch error: invalid URL."));
                         } else {
                             out.push(alloc::format!("Fetch: {}", request_urls[0]));
-
-                            let mut selected_payload: Option<Vec<u8>> = None;
-                            let mut selected_url: Option<String> = None;
-                            let mut hard_error = false;
-
-                            let mut pump = || self.pump_ui_while_blocked_net();
-                            for (idx, candidate_url) in request_urls.iter().enumerate() {
-                                if idx > 0 {
-                                    out.push(alloc::format!("Fetch retry: {}", candidate_url));
-                                }
-
-                                let Some(raw) =
-                                    crate::net::http_get_request_bytes(candidate_url.as_str(), &mut pump)
-                                else {
-                                    continue;
-                                };
-                                let (status_code, payload) =
-                                    Self::extract_http_status_and_body_bytes(raw.as_slice());
-
-                                if let Some(code) = status_code {
-                                    if (300..400).contains(&code) {
-                                        out.push(alloc::format!(
-                                            "Fetch error: HTTP {} redirect not supported yet. Use final URL.",
-                                            code
-                                        ));
-                                        hard_error = true;
-                                        break;
-                                    } else if code >= 400 {
-                                        if code == 404 && idx + 1 < request_urls.len() {
-                                            continue;
-                                        }
-                                        out.push(alloc::format!("Fetch error: HTTP {}", code));
-                                        hard_error = true;
-                                        break;
-                                    }
-                                }
-
-                                selected_payload = Some(payload);
-                                selected_url = Some(candidate_url.clone());
-                                break;
-                            }
-
-                            if !hard_error {
-                                if let Some(mut payload) = selected_payload {
-                                    if payload.is_empty() {
-                                        out.push(String::from("Fetch error: empty response body."));
-                                    } else {
-                                        if payload.len() > FETCH_MAX_FILE_BYTES {
-                                            payload.truncate(FETCH_MAX_FILE_BYTES);
-                                            out.push(alloc::format!(
-                                                "Fetch warning: truncated to {} bytes.",
-                                                FETCH_MAX_FILE_BYTES
-                                            ));
-                                        }
-
-                                        if let Some(final_url) = selected_url {
-                                            if final_url != request_urls[0] {
-                                                out.push(alloc::format!("Fetch final URL: {}", final_url));
-                                            }
-                                        }
-
-                                        match fat.write_text_file_in_dir(
-                                            current_cluster,
-                                            file_name.as_str(),
-                                            payload.as_slice(),
-                                        ) {
-                                            Ok(()) => {
-                                                out.push(alloc::format!(
-                                                    "Saved {} bytes to {}",
-                                                    payload.len(),
-                                                    file_name
-                                                ));
-                                                if file_name.ends_with(".RB") {
-                                                    out.push(alloc::format!("Run with: ruby {}", file_name));
-                                                } else if repo_mode {
-                                                    out.push(String::from(
-                                                        "Tip: fetch a .rb file from repo and run `ruby <file>.`",
-                                                    ));
-                                                }
-                                            }
-                                            Err(err) => {
-                                                out.push(alloc::format!("Fetch error: {}", err));
-                                            }
-                                        }
-                                    }
-                                } else {
-                                    out.push(String::from("Fetch error: network request failed."));
-                                }
+                            let mut fetch = PendingFetch {
+                                win_id,
+                                request_id: 0,
+                                candidates: request_urls,
+                                index: 0,
+                                file_name,
+                                dir_cluster: current_cluster,
+                                repo_mode,
+                            };
+                            if fetch.submit() {
+                                out.push(String::from("Fetch: descargando en segundo plano..."));
+                                self.pending_fetches.push(fetch);
+                            } else {
+                                out.push(String::from("Fetch error: network request failed."));
                             }
                         }
                     }
@@ -39376,7 +44379,7 @@ impl Compositor {
                             ));
                             if !crate::linux_guest_efi_available() {
                                 out.push(String::from(
-                                    "  tip: copia Linux guest EFI en /EFI/LINUX/BOOTX64.EFI",
+                                    "  tip: copia Linux guest EFI en /EFI/LINUX/BOOTX64.EFI o vmlinuz + initrd.img en /EFI/LINUX",
                                 ));
                             }
                         }
@@ -39411,7 +44414,7 @@ impl Compositor {
                                             .as_str(),
                                         );
                                         win.add_output(
-                                            "Rutas esperadas: /EFI/LINUX/BOOTX64.EFI o /EFI/BOOT/LINUX.EFI",
+                                            "Rutas esperadas: /EFI/LINUX/BOOTX64.EFI, /EFI/BOOT/LINUX.EFI o /EFI/LINUX/vmlinuz",
                                         );
                                     }
                                 }
@@ -43119,57 +48122,9 @@ impl Compositor {
         if fat.bytes_per_sector == 0 {
             if verb == "help" {
                 if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
-                    win.add_output("Available commands:");
-                    win.add_output("  ls - List files");
-                    win.add_output("  cd <dir> - Change dir");
-                    win.add_output("  cat <file> - Read file");
-                    win.add_output("  cp <src> <dst> - Copy file (supports simple paths)");
-                    win.add_output("  mv <src> <dst> - Move/rename file");
-                    win.add_output("  disks - List USB/NVMe/HDD BlockIO devices");
-                    win.add_output("  vols - List mountable FAT32/exFAT volumes");
-                    win.add_output("  mount <n> - Mount FAT32/exFAT from 'disks' index");
-                    win.add_output("  unmount - Unmount active volume");
-                    win.add_output("  cpdev <src_dev> <src_path> <dst_dev> <dst_path> - Copy file between devices");
-                    win.add_output("  net - Show transport/IP/failover status");
-                    win.add_output("  net dhcp - Request dynamic IP via DHCP");
-                    win.add_output("  net static - Apply default static IP");
-                    win.add_output("  net static <ip> <prefijo> <gateway> - Apply custom static IP");
-                    win.add_output("  net mode - Show current IP mode");
-                    win.add_output("  net https <on|off|status> - HTTPS compatibility");
-                    win.add_output("  net diag - Dump Intel Ethernet RX/TX registers");
-                    win.add_output("  wifi - Show WiFi status");
-                    win.add_output("  wifi scan - Scan WiFi networks");
-                    win.add_output("  wifi connect <ssid> <clave> - Save profile/connect");
-                    win.add_output("  wifi disconnect - Disconnect WiFi");
-                    win.add_output("  wifi failover <ethernet|wifi|status> - Auto priority");
-                    win.add_output("  fetch <url> [file_8_3] - Download file from network");
-                    win.add_output("  web backend <builtin|litehtml|litehtmlrt|servort|vaev|webkit|servohost|cef|status> - Browser renderer");
-                    win.add_output("  web litehtmlrt <status|target <path>> - Runtime LinuxRT para litehtml");
-                    win.add_output("  web servort <status|target <path>|mode <safe|real|status>|open <url>|frame|input ...> - Runtime LinuxRT para Servo");
-                    win.add_output("  web vaev status - Embedded Vaev bridge diagnostics");
-                    win.add_output("  web vaev input <click x y|scroll d|key K|text T|back|forward|reload>");
-                    win.add_output("  web native <on|off|status> - Native DOM/layout/raster engine");
-                    win.add_output("  web webkit <status|endpoint|ping|open|frame|input> - Host WebKit bridge");
-                    win.add_output("  web servohost <status|endpoint|ping|open|frame|input> - Host Servo bridge (alias)");
-                    win.add_output("  wry ... - alias de web webkit");
-                    win.add_output("  servohost ... - alias de web servohost");
-                    win.add_output("  servort ... - alias de web servort");
-                    win.add_output("  mem - Show memory statistics");
-                    win.add_output("  acpi - Show ACPI S3 diagnostics");
-                    win.add_output("  suspend - Try ACPI S3 suspend");
-                    win.add_output("  stream <status|flush|auto on|auto off|auto status> - Scheduler de salida multitarea para terminal/procesos");
-                    win.add_output("  tasks <status|clear|cancel <id>|tune ...> - Cola/throttle de tareas de sistema (cp/mv/cpdev en background)");
-                    win.add_output("  install [--autoport] <package.rpx|package.zip|package.tar|package.tar.gz|package.deb|setup.exe> [app_id] - Install package");
-                    win.add_output("  entry <archivo> [app_id] - Generic installer entry point");
-                    win.add_output("  linux inspect <elf> | linux run <elf> [args...] | linux runreal <elf> [args...] | linux runrealx <elf> [args...] | linux launch <elf> [args...] | linux launchmeta [--strict] <elf> | linux transfer <on|off|status> | linux runtime <quick|deep|status> | linux guest <status|start|rootfs|share|prefix|map> | linux app <run|map|status> | linux proc <start|startm|startx|startmx|status|step|stop> | linux runloop <start|startx|startm|startmx|status|step|stop> | linux bridge <open|close|status|test>");
-                    win.add_output("  host newlib porting - scripts/newlib_port.sh (scaffold/build/doctor)");
-                    win.add_output("  ruby -e <code> | ruby <file.rb> - Ruby subset runtime");
-                    win.add_output("  runapp <layout.rml> - Open .RML app in App Runner");
-                    win.add_output("  ide - Open Redux Studio (editor interno + preview + install/export .rpx)");
-                    win.add_output("  clear - Clear screen");
-                    win.add_output("  help - Show this help");
-                    win.add_output("  cppdoom - Launch CPP-DOOM native app");
-                    win.add_output("  shell - Launch external UEFI Shell image");
+                    for line in crate::i18n::desktop_help_lines().iter() {
+                        win.add_output(line.as_str());
+                    }
                 }
                 return;
             } else if verb == "clear" {
@@ -43200,9 +48155,7 @@ impl Compositor {
         let mut special_handled = false;
 
         if verb == "help" {
-            output = String::from(
-                "Available commands:\n  ls - List files\n  cd <dir> - Change dir\n  cat <file> - Read file\n  cp <src> <dst> - Copy file\n  mv <src> <dst> - Move/rename file\n  disks - List USB/NVMe/HDD BlockIO devices\n  vols - List mountable FAT32/exFAT volumes\n  mount <n> - Mount FAT32/exFAT from 'disks' index\n  unmount - Unmount active volume\n  cpdev <src_dev> <src_path> <dst_dev> <dst_path> - Copy file between devices\n  net - Show transport/IP/failover status\n  net dhcp - Request dynamic IP via DHCP\n  net static - Apply default static IP\n  net static <ip> <prefijo> <gateway> - Apply custom static IP\n  net mode - Show current IP mode\n  net https <on|off|status> - HTTPS compatibility\n  net diag - Dump Intel Ethernet RX/TX registers\n  wifi - Show WiFi status\n  wifi scan - Scan WiFi networks\n  wifi connect <ssid> <clave> - Save profile/connect\n  wifi disconnect - Disconnect WiFi\n  wifi failover <ethernet|wifi|status> - Auto priority\n  fetch <url> [file_8_3] - Download file from network\n  web backend <builtin|litehtml|litehtmlrt|servort|vaev|webkit|servohost|cef|status> - Browser renderer\n  web litehtmlrt <status|target <path>> - Runtime LinuxRT para litehtml\n  web servort <status|target <path>|mode <safe|real|status>|open <url>|frame|input ...> - Runtime LinuxRT para Servo\n  web vaev status - Embedded Vaev bridge diagnostics\n  web vaev input <click x y|scroll d|key K|text T|back|forward|reload>\n  web native <on|off|status> - Native DOM/layout/raster engine\n  web webkit <status|endpoint|ping|open|frame|input> - Host WebKit bridge\n  web servohost <status|endpoint|ping|open|frame|input> - Host Servo bridge (alias)\n  wry ... - alias de web webkit\n  servohost ... - alias de web servohost\n  servort ... - alias de web servort\n  mem - Show memory statistics\n  stream <status|flush|auto on|auto off|auto status> - Scheduler de salida multitarea para terminal/procesos\n  tasks <status|clear|cancel <id>|tune ...> - Cola/throttle de tareas de sistema (cp/mv/cpdev en background)\n  install [--autoport] <package.rpx|package.zip|package.tar|package.tar.gz|package.deb|setup.exe> [app_id] - Install package\n  entry <archivo> [app_id] - Generic installer entry point\n  linux inspect <elf> | linux run <elf> [args...] | linux runreal <elf> [args...] | linux runrealx <elf> [args...] | linux launch <elf> [args...] | linux launchmeta [--strict] <elf> | linux transfer <on|off|status> | linux runtime <quick|deep|status> | linux guest <status|start|rootfs|share|prefix|map> | linux app <run|map|status> | linux proc <start|startm|startx|startmx|status|step|stop> | linux runloop <start|startx|startm|startmx|status|step|stop> | linux bridge <open|close|status|test>\n  host newlib porting - scripts/newlib_port.sh (scaffold/build/doctor)\n  ruby -e <code> | ruby <file.rb> - Ruby subset runtime\n  runapp <layout.rml> - Open .RML app in App Runner\n  ide - Open Redux Studio (editor interno + preview + install/export .rpx)\n  clear - Clear screen\n  help - Show this help\n  cppdoom - Launch CPP-DOOM native app\n  shell - Launch external UEFI Shell image",
-            );
+            output = crate::i18n::desktop_help_lines().join("\n");
         } else if verb == "clear" {
             if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
                 win.clear_terminal_output();
@@ -43210,15 +48163,24 @@ impl Compositor {
             special_handled = true;
         } else if verb == "ls" {
             if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
-                if let Ok(entries) = fat.read_dir_entries(win.current_dir_cluster) {
+                if let Err(e) = crate::perm::authorize_list(fat, win.current_dir_cluster) {
+                    output = alloc::format!("ls: {}", e);
+                } else if let Ok(entries) = fat.read_dir_entries(win.current_dir_cluster) {
                     if win.current_dir_cluster == fat.root_cluster {
                         if let Some(label) = Self::volume_label_text(fat) {
                             output.push_str(&alloc::format!("[VOL] {}\n", label));
                         }
                     }
 
+                    let links = crate::fsmeta::links_in(fat, win.current_dir_cluster);
                     for entry in entries.iter() {
                         if entry.valid {
+                            let name = entry.full_name();
+                            let lower = name.to_ascii_lowercase();
+                            if let Some((_, target)) = links.iter().find(|(link, _)| *link == lower) {
+                                output.push_str(&alloc::format!("[LINK] {} -> {}\n", name, target));
+                                continue;
+                            }
                             let type_tag = if entry.file_type == FileType::Directory {
                                 "DIR"
                             } else {
@@ -43227,7 +48189,7 @@ impl Compositor {
                             output.push_str(&alloc::format!(
                                 "[{}] {} ({} bytes)\n",
                                 type_tag,
-                                entry.full_name(),
+                                name,
                                 entry.size
                             ));
                         }
@@ -43314,10 +48276,39 @@ impl Compositor {
                         }
                     }
                     if !found {
-                        output = String::from("Directory not found.");
+                        // Links and multi-part paths go through the VFS metadata.
+                        match crate::fsmeta::resolve(fat, win.current_dir_cluster, target, true) {
+                            Ok(place) => match place.dir_cluster(fat.root_cluster) {
+                                Some(cluster) => {
+                                    win.current_dir_cluster = cluster;
+                                    let shown = place.path.trim_matches('/');
+                                    if place.path.starts_with('/') {
+                                        let label =
+                                            Self::volume_label_text(fat).unwrap_or_else(|| String::from("REDUX"));
+                                        win.current_path = alloc::format!("{}/", label);
+                                    } else if !win.current_path.ends_with('/') {
+                                        win.current_path.push('/');
+                                    }
+                                    if !shown.is_empty() {
+                                        win.current_path.push_str(shown);
+                                        win.current_path.push('/');
+                                    }
+                                }
+                                None => output = String::from("Directory not found."),
+                            },
+                            Err(_) => output = String::from("Directory not found."),
+                        }
                     }
                 }
             }
+        } else if verb == "ln" || verb == "readlink" || verb == "xattr" {
+            let cwd = self.terminal_current_cluster(win_id, fat);
+            output = crate::fsmeta::command_lines(fat, cwd, verb.as_str(), arg_raw).join("\n");
+        } else if verb == "chmod" || verb == "chown" || verb == "stat" {
+            let cwd = self.terminal_current_cluster(win_id, fat);
+            output = crate::perm::command_lines(fat, cwd, verb.as_str(), arg_raw).join("\n");
+        } else if verb == "chkdsk" {
+            output = crate::chkdsk::command_lines(fat, arg_raw).join("\n");
         } else if verb == "notepad" {
             self.open_notepad_blank();
             if let Some(win) = self.windows.iter_mut().find(|w| w.id == win_id) {
@@ -43335,6 +48326,20 @@ impl Compositor {
                         let mut found = false;
                         for entry in entries.iter() {
                             if entry.valid && entry.matches_name(filename) {
+                                found = true;
+                                if let Err(e) =
+                                    crate::perm::authorize_open(fat, win.current_dir_cluster, &entry.full_name())
+                                {
+                                    output = alloc::format!("cat: {}", e);
+                                    break;
+                                }
+                                let entry = match crate::fsmeta::follow(fat, win.current_dir_cluster, entry) {
+                                    Ok(entry) => entry,
+                                    Err(e) => {
+                                        output = alloc::format!("cat: {}", e);
+                                        break;
+                                    }
+                                };
                                 let target = (entry.size as usize).min(16 * 1024);
                                 let mut buffer = Vec::new();
                                 buffer.resize(target, 0);
@@ -43353,7 +48358,6 @@ impl Compositor {
                                         output = String::from("Error reading file.");
                                     }
                                 }
-                                found = true;
                                 break;
                             }
                         }
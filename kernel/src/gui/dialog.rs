@@ -0,0 +1,874 @@
+//! Modal dialogs: message boxes, text prompts, the file open/save dialog and
+//! the print dialog.
+//!
+//! A `Dialog` lays itself out, draws, and turns clicks and keys into a
+//! `DialogOutcome`. The compositor keeps at most one open, holds back all other
+//! input while it is, lists folders for the file dialog and acts on the outcome.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::{Point, Rect, SpecialKey};
+use crate::framebuffer;
+
+const MESSAGE_W: u32 = 420;
+const PROMPT_H: u32 = 132;
+const FILE_W: u32 = 520;
+const FILE_H: u32 = 380;
+const PRINT_H: u32 = 260;
+/// Most copies the print dialog asks for.
+const COPIES_MAX: u32 = 99;
+const BUTTON_W: u32 = 92;
+const BUTTON_H: u32 = 24;
+const BUTTON_GAP: i32 = 8;
+const ROW_H: u32 = 18;
+const LINE_H: i32 = 12;
+/// Characters per wrapped message line at the 6 px advance of the 5x7 font.
+const MESSAGE_COLS: usize = 64;
+const NAME_MAX: usize = 64;
+const WHEEL_ROWS: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FileDialogMode {
+    Open,
+    Save,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FileEntry {
+    pub name: String,
+    pub cluster: u32,
+    pub size: u32,
+    pub is_directory: bool,
+}
+
+/// What was picked in a file dialog. When saving under a new name `exists`
+/// is false and `cluster`/`size` are 0.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FileChoice {
+    pub device_index: Option<usize>,
+    pub dir_cluster: u32,
+    pub dir_path: String,
+    pub name: String,
+    pub cluster: u32,
+    pub size: u32,
+    pub exists: bool,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DialogOutcome {
+    /// Input was consumed; the dialog stays open.
+    None,
+    /// Index into the buttons a message box was created with.
+    Button(usize),
+    Text(String),
+    /// The file dialog wants `set_listing` for another folder.
+    Browse {
+        cluster: u32,
+        path: String,
+    },
+    File(FileChoice),
+    /// The print dialog's printer (an index into its list) and copies.
+    Print {
+        printer: usize,
+        copies: u32,
+    },
+    Cancel,
+}
+
+pub struct FileBrowser {
+    pub mode: FileDialogMode,
+    pub device_index: Option<usize>,
+    pub dir_cluster: u32,
+    pub dir_path: String,
+    /// Folders above the current one, nearest last, for the ".." row.
+    parents: Vec<(u32, String)>,
+    entries: Vec<FileEntry>,
+    /// Upper-case extension to list, e.g. "BMP". Folders are always listed.
+    extension: Option<&'static str>,
+    /// Row 0 is ".." when there is a parent folder.
+    selected: usize,
+    scroll: usize,
+    pub name: String,
+    pub status: String,
+}
+
+impl FileBrowser {
+    fn has_parent(&self) -> bool {
+        !self.parents.is_empty()
+    }
+
+    fn row_count(&self) -> usize {
+        self.entries.len() + self.has_parent() as usize
+    }
+
+    /// `None` for the ".." row or past the end.
+    fn entry_at_row(&self, row: usize) -> Option<&FileEntry> {
+        let index = if self.has_parent() { row.checked_sub(1)? } else { row };
+        self.entries.get(index)
+    }
+
+    fn matches_extension(&self, name: &str) -> bool {
+        match self.extension {
+            Some(ext) => name
+                .rsplit_once('.')
+                .map(|(_, e)| e.eq_ignore_ascii_case(ext))
+                .unwrap_or(false),
+            None => true,
+        }
+    }
+
+    /// Show `entries` as the contents of `cluster`. Moving back up to a folder
+    /// already on the parent stack pops it; anything else goes one level down.
+    pub fn set_listing(&mut self, cluster: u32, path: &str, mut entries: Vec<FileEntry>) {
+        // Folders on mounted volumes all have cluster 0; the path tells them apart.
+        if let Some(pos) = self.parents.iter().position(|(c, p)| *c == cluster && p == path) {
+            self.parents.truncate(pos);
+        } else if cluster != self.dir_cluster || path != self.dir_path {
+            let previous = core::mem::take(&mut self.dir_path);
+            self.parents.push((self.dir_cluster, previous));
+        }
+        self.dir_cluster = cluster;
+        self.dir_path = String::from(path);
+        entries.retain(|e| e.name != "." && e.name != ".." && (e.is_directory || self.matches_extension(&e.name)));
+        entries.sort_by(|a, b| {
+            b.is_directory.cmp(&a.is_directory).then_with(|| {
+                a.name
+                    .bytes()
+                    .map(|c| c.to_ascii_lowercase())
+                    .cmp(b.name.bytes().map(|c| c.to_ascii_lowercase()))
+            })
+        });
+        self.entries = entries;
+        self.selected = 0;
+        self.scroll = 0;
+        self.status.clear();
+    }
+
+    fn select(&mut self, row: usize, visible: usize) {
+        if row >= self.row_count() {
+            return;
+        }
+        self.selected = row;
+        if self.mode == FileDialogMode::Save {
+            if let Some(entry) = self.entry_at_row(row).filter(|e| !e.is_directory) {
+                self.name = entry.name.clone();
+            }
+        }
+        if row < self.scroll {
+            self.scroll = row;
+        } else if visible > 0 && row >= self.scroll + visible {
+            self.scroll = row + 1 - visible;
+        }
+    }
+
+    fn child_path(&self, name: &str) -> String {
+        let mut path = self.dir_path.clone();
+        if !path.ends_with('/') {
+            path.push('/');
+        }
+        path.push_str(name);
+        path.push('/');
+        path
+    }
+
+    /// Open the selected row: enter a folder, or pick a file.
+    fn activate_selected(&mut self) -> DialogOutcome {
+        if self.has_parent() && self.selected == 0 {
+            let (cluster, path) = self.parents[self.parents.len() - 1].clone();
+            return DialogOutcome::Browse { cluster, path };
+        }
+        let Some(entry) = self.entry_at_row(self.selected).cloned() else {
+            return DialogOutcome::None;
+        };
+        if entry.is_directory {
+            return DialogOutcome::Browse {
+                cluster: entry.cluster,
+                path: self.child_path(&entry.name),
+            };
+        }
+        match self.mode {
+            FileDialogMode::Open => DialogOutcome::File(self.choice(entry.name.clone(), Some(&entry))),
+            FileDialogMode::Save => {
+                self.name = entry.name;
+                self.choose_name()
+            }
+        }
+    }
+
+    /// Save under the typed name, adding the dialog's extension when it has none.
+    fn choose_name(&mut self) -> DialogOutcome {
+        let trimmed = self.name.trim();
+        let invalid = trimmed.is_empty()
+            || trimmed == "."
+            || trimmed == ".."
+            || trimmed
+                .bytes()
+                .any(|b| matches!(b, b'/' | b'\\' | b':' | b'*' | b'?' | b'"' | b'<' | b'>' | b'|'));
+        if invalid {
+            self.status = String::from("Nombre invalido.");
+            return DialogOutcome::None;
+        }
+        let mut name = String::from(trimmed);
+        if let Some(ext) = self.extension {
+            if !name.contains('.') {
+                name.push('.');
+                name.push_str(ext);
+            }
+        }
+        let existing = self
+            .entries
+            .iter()
+            .find(|e| e.name.eq_ignore_ascii_case(&name))
+            .cloned();
+        if existing.as_ref().map(|e| e.is_directory).unwrap_or(false) {
+            self.status = String::from("Ya existe una carpeta con ese nombre.");
+            return DialogOutcome::None;
+        }
+        DialogOutcome::File(self.choice(name, existing.as_ref()))
+    }
+
+    fn choice(&self, name: String, existing: Option<&FileEntry>) -> FileChoice {
+        FileChoice {
+            device_index: self.device_index,
+            dir_cluster: self.dir_cluster,
+            dir_path: self.dir_path.clone(),
+            name,
+            cluster: existing.map(|e| e.cluster).unwrap_or(0),
+            size: existing.map(|e| e.size).unwrap_or(0),
+            exists: existing.is_some(),
+        }
+    }
+}
+
+enum Body {
+    Message {
+        lines: Vec<String>,
+        buttons: Vec<String>,
+    },
+    Prompt {
+        label: String,
+        value: String,
+        max_len: usize,
+    },
+    File(FileBrowser),
+    Print {
+        printers: Vec<String>,
+        selected: usize,
+        copies: u32,
+    },
+}
+
+pub struct Dialog {
+    pub title: String,
+    body: Body,
+    /// Button that Enter presses.
+    focus: usize,
+}
+
+fn wrap_lines(text: &str, cols: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            if !line.is_empty() && line.len() + 1 + word.len() > cols {
+                lines.push(core::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn trim_to(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => &text[..idx],
+        None => text,
+    }
+}
+
+/// The last `max_chars` characters, for paths and input that outgrow a field.
+fn tail_to(text: &str, max_chars: usize) -> &str {
+    let count = text.chars().count();
+    match text.char_indices().nth(count.saturating_sub(max_chars)) {
+        Some((idx, _)) => &text[idx..],
+        None => text,
+    }
+}
+
+fn fill(rect: Rect, color: u32) {
+    framebuffer::rect(
+        rect.x.max(0) as usize,
+        rect.y.max(0) as usize,
+        rect.width as usize,
+        rect.height as usize,
+        color,
+    );
+}
+
+fn text(x: i32, y: i32, value: &str, color: u32) {
+    framebuffer::draw_text_5x7(x.max(0) as usize, y.max(0) as usize, value, color);
+}
+
+impl Dialog {
+    pub fn message(title: &str, text: &str, buttons: &[&str]) -> Self {
+        Self {
+            title: String::from(title),
+            body: Body::Message {
+                lines: wrap_lines(text, MESSAGE_COLS),
+                buttons: buttons.iter().map(|b| String::from(*b)).collect(),
+            },
+            focus: 0,
+        }
+    }
+
+    /// "Are you sure?" box: button 0 accepts, button 1 cancels and has focus.
+    pub fn confirm(title: &str, text: &str, accept: &str) -> Self {
+        let mut dialog = Self::message(title, text, &[accept, "Cancelar"]);
+        dialog.focus = 1;
+        dialog
+    }
+
+    pub fn prompt(title: &str, label: &str, initial: &str, max_len: usize) -> Self {
+        Self {
+            title: String::from(title),
+            body: Body::Prompt {
+                label: String::from(label),
+                value: String::from(initial),
+                max_len,
+            },
+            focus: 0,
+        }
+    }
+
+    /// File dialog starting in `dir_cluster`; call `set_listing` with its
+    /// contents before the first frame.
+    pub fn file(
+        title: &str,
+        mode: FileDialogMode,
+        device_index: Option<usize>,
+        dir_cluster: u32,
+        dir_path: &str,
+        default_name: &str,
+        extension: Option<&'static str>,
+    ) -> Self {
+        Self {
+            title: String::from(title),
+            body: Body::File(FileBrowser {
+                mode,
+                device_index,
+                dir_cluster,
+                dir_path: String::from(dir_path),
+                parents: Vec::new(),
+                entries: Vec::new(),
+                extension,
+                selected: 0,
+                scroll: 0,
+                name: String::from(default_name),
+                status: String::new(),
+            }),
+            focus: 0,
+        }
+    }
+
+    /// Print dialog over `printers` (names), with `selected` preselected.
+    pub fn print(title: &str, printers: Vec<String>, selected: usize) -> Self {
+        let selected = selected.min(printers.len().saturating_sub(1));
+        Self {
+            title: String::from(title),
+            body: Body::Print {
+                printers,
+                selected,
+                copies: 1,
+            },
+            focus: 0,
+        }
+    }
+
+    pub fn file_browser(&self) -> Option<&FileBrowser> {
+        match &self.body {
+            Body::File(browser) => Some(browser),
+            _ => None,
+        }
+    }
+
+    pub fn file_browser_mut(&mut self) -> Option<&mut FileBrowser> {
+        match &mut self.body {
+            Body::File(browser) => Some(browser),
+            _ => None,
+        }
+    }
+
+    /// Centered in `bounds`, the desktop area above the taskbar.
+    pub fn rect(&self, bounds: Rect) -> Rect {
+        let (w, h) = match &self.body {
+            Body::Message { lines, .. } => (MESSAGE_W, 44 + lines.len() as u32 * LINE_H as u32 + 16 + BUTTON_H + 12),
+            Body::Prompt { .. } => (MESSAGE_W, PROMPT_H),
+            Body::File(_) => (FILE_W, FILE_H),
+            Body::Print { .. } => (MESSAGE_W, PRINT_H),
+        };
+        let w = w.min(bounds.width);
+        let h = h.min(bounds.height);
+        Rect::new(
+            bounds.x + (bounds.width - w) as i32 / 2,
+            bounds.y + (bounds.height - h) as i32 / 2,
+            w,
+            h,
+        )
+    }
+
+    fn buttons(&self) -> Vec<&str> {
+        match &self.body {
+            Body::Message { buttons, .. } => buttons.iter().map(|b| b.as_str()).collect(),
+            Body::Prompt { .. } => alloc::vec!["Aceptar", "Cancelar"],
+            Body::File(browser) => match browser.mode {
+                FileDialogMode::Open => alloc::vec!["Abrir", "Cancelar"],
+                FileDialogMode::Save => alloc::vec!["Guardar", "Cancelar"],
+            },
+            Body::Print { .. } => alloc::vec!["Imprimir", "Cancelar"],
+        }
+    }
+
+    /// Buttons sit right-aligned along the bottom edge, in creation order.
+    fn button_rect(rect: Rect, count: usize, index: usize) -> Rect {
+        let from_right = (count - index) as i32;
+        Rect::new(
+            rect.x + rect.width as i32 - 10 - from_right * (BUTTON_W as i32 + BUTTON_GAP) + BUTTON_GAP,
+            rect.y + rect.height as i32 - BUTTON_H as i32 - 10,
+            BUTTON_W,
+            BUTTON_H,
+        )
+    }
+
+    fn field_rect(&self, rect: Rect) -> Rect {
+        match &self.body {
+            Body::File(_) => Rect::new(
+                rect.x + 64,
+                rect.y + rect.height as i32 - 68,
+                rect.width.saturating_sub(74),
+                20,
+            ),
+            _ => Rect::new(rect.x + 10, rect.y + 50, rect.width.saturating_sub(20), 22),
+        }
+    }
+
+    fn list_rect(rect: Rect) -> Rect {
+        Rect::new(
+            rect.x + 10,
+            rect.y + 44,
+            rect.width.saturating_sub(20),
+            rect.height.saturating_sub(44 + 78),
+        )
+    }
+
+    fn visible_rows(rect: Rect) -> usize {
+        (Self::list_rect(rect).height.saturating_sub(4) / ROW_H) as usize
+    }
+
+    fn row_rect(rect: Rect, visible_row: usize) -> Rect {
+        let list = Self::list_rect(rect);
+        Rect::new(
+            list.x + 2,
+            list.y + 2 + (visible_row as u32 * ROW_H) as i32,
+            list.width.saturating_sub(4),
+            ROW_H,
+        )
+    }
+
+    /// The "-" and "+" boxes next to the copies count of the print dialog.
+    fn copies_rect(rect: Rect, plus: bool) -> Rect {
+        Rect::new(
+            rect.x + 70 + if plus { 56 } else { 0 },
+            rect.y + rect.height as i32 - 68,
+            20,
+            20,
+        )
+    }
+
+    /// What pressing button `index` means.
+    fn press(&mut self, index: usize) -> DialogOutcome {
+        match &mut self.body {
+            Body::Message { .. } => DialogOutcome::Button(index),
+            Body::Prompt { value, .. } if index == 0 => DialogOutcome::Text(value.clone()),
+            Body::File(browser) if index == 0 => match browser.mode {
+                FileDialogMode::Open => browser.activate_selected(),
+                FileDialogMode::Save => browser.choose_name(),
+            },
+            Body::Print {
+                printers,
+                selected,
+                copies,
+            } if index == 0 && !printers.is_empty() => DialogOutcome::Print {
+                printer: *selected,
+                copies: *copies,
+            },
+            _ => DialogOutcome::Cancel,
+        }
+    }
+
+    pub fn key(&mut self, key: Option<char>, special: Option<SpecialKey>, bounds: Rect) -> DialogOutcome {
+        let visible = Self::visible_rows(self.rect(bounds));
+        match key {
+            Some('\x1b') => return DialogOutcome::Cancel,
+            Some('\n') | Some('\r') => {
+                let focus = self.focus;
+                return self.press(focus);
+            }
+            _ => {}
+        }
+        let count = self.buttons().len();
+        match &mut self.body {
+            Body::Message { .. } => match (key, special) {
+                (Some('\t'), _) | (_, Some(SpecialKey::Right)) => self.focus = (self.focus + 1) % count.max(1),
+                (_, Some(SpecialKey::Left)) => self.focus = (self.focus + count.max(1) - 1) % count.max(1),
+                _ => {}
+            },
+            Body::Prompt { value, max_len, .. } => match key {
+                Some('\x08') | Some('\x7f') => {
+                    value.pop();
+                }
+                Some(ch) if !ch.is_control() && value.chars().count() < *max_len => value.push(ch),
+                _ => {}
+            },
+            Body::File(browser) => match (key, special) {
+                (_, Some(SpecialKey::Up)) => browser.select(browser.selected.saturating_sub(1), visible),
+                (_, Some(SpecialKey::Down)) => browser.select(browser.selected + 1, visible),
+                (Some('\x08'), _) | (Some('\x7f'), _) => {
+                    if browser.mode == FileDialogMode::Save {
+                        browser.name.pop();
+                    } else if let Some((cluster, path)) = browser.parents.last().cloned() {
+                        return DialogOutcome::Browse { cluster, path };
+                    }
+                }
+                (Some(ch), _)
+                    if browser.mode == FileDialogMode::Save
+                        && ch.is_ascii()
+                        && !ch.is_control()
+                        && browser.name.len() < NAME_MAX =>
+                {
+                    browser.name.push(ch)
+                }
+                _ => {}
+            },
+            Body::Print {
+                printers,
+                selected,
+                copies,
+            } => match (key, special) {
+                (_, Some(SpecialKey::Up)) => *selected = selected.saturating_sub(1),
+                (_, Some(SpecialKey::Down)) => *selected = (*selected + 1).min(printers.len().saturating_sub(1)),
+                (Some('+'), _) => *copies = (*copies + 1).min(COPIES_MAX),
+                (Some('-'), _) => *copies = copies.saturating_sub(1).max(1),
+                _ => {}
+            },
+        }
+        DialogOutcome::None
+    }
+
+    /// Left click at `p`. `double` is the second click of a double-click, which
+    /// opens a folder or file row.
+    pub fn click(&mut self, p: Point, bounds: Rect, double: bool) -> DialogOutcome {
+        let rect = self.rect(bounds);
+        let count = self.buttons().len();
+        if let Some(index) = (0..count).find(|&i| Self::button_rect(rect, count, i).contains(p)) {
+            self.focus = index;
+            return self.press(index);
+        }
+        let visible = Self::visible_rows(rect);
+        if let Body::File(browser) = &mut self.body {
+            if let Some(row) = (0..visible).find(|&r| Self::row_rect(rect, r).contains(p)) {
+                let row = browser.scroll + row;
+                if row < browser.row_count() {
+                    browser.select(row, visible);
+                    if double {
+                        return browser.activate_selected();
+                    }
+                }
+            }
+        }
+        if let Body::Print {
+            printers,
+            selected,
+            copies,
+        } = &mut self.body
+        {
+            if let Some(row) = (0..visible.min(printers.len())).find(|&r| Self::row_rect(rect, r).contains(p)) {
+                *selected = row;
+                if double {
+                    return DialogOutcome::Print {
+                        printer: row,
+                        copies: *copies,
+                    };
+                }
+            } else if Self::copies_rect(rect, false).contains(p) {
+                *copies = copies.saturating_sub(1).max(1);
+            } else if Self::copies_rect(rect, true).contains(p) {
+                *copies = (*copies + 1).min(COPIES_MAX);
+            }
+        }
+        DialogOutcome::None
+    }
+
+    pub fn wheel(&mut self, delta: i32, bounds: Rect) {
+        let visible = Self::visible_rows(self.rect(bounds));
+        if let Body::File(browser) = &mut self.body {
+            let max_scroll = browser.row_count().saturating_sub(visible);
+            if delta > 0 {
+                browser.scroll = browser.scroll.saturating_sub(WHEEL_ROWS);
+            } else if delta < 0 {
+                browser.scroll = (browser.scroll + WHEEL_ROWS).min(max_scroll);
+            }
+        }
+    }
+
+    pub fn draw(&self, bounds: Rect, pointer: Point) {
+        let rect = self.rect(bounds);
+        fill(rect, 0x10283A);
+        fill(Rect::new(rect.x, rect.y, rect.width, 1), 0x7BB9E3);
+        fill(
+            Rect::new(rect.x, rect.y + rect.height as i32 - 1, rect.width, 1),
+            0x091521,
+        );
+        let cols = (rect.width as usize).saturating_sub(20) / 6;
+        text(rect.x + 10, rect.y + 12, trim_to(&self.title, cols), 0xEAF6FF);
+
+        match &self.body {
+            Body::Message { lines, .. } => {
+                for (i, line) in lines.iter().enumerate() {
+                    text(
+                        rect.x + 10,
+                        rect.y + 36 + i as i32 * LINE_H,
+                        trim_to(line, cols),
+                        0xD6E6F5,
+                    );
+                }
+            }
+            Body::Prompt { label, value, .. } => {
+                text(rect.x + 10, rect.y + 34, trim_to(label, cols), 0xA6C7E1);
+                self.draw_field(rect, value, true);
+            }
+            Body::File(browser) => {
+                text(rect.x + 10, rect.y + 28, tail_to(&browser.dir_path, cols), 0xA6C7E1);
+                self.draw_file_list(rect, browser, pointer);
+                text(rect.x + 10, rect.y + rect.height as i32 - 62, "Nombre:", 0xA6C7E1);
+                let name = match browser.mode {
+                    FileDialogMode::Save => browser.name.as_str(),
+                    FileDialogMode::Open => browser
+                        .entry_at_row(browser.selected)
+                        .map(|e| e.name.as_str())
+                        .unwrap_or(""),
+                };
+                self.draw_field(rect, name, browser.mode == FileDialogMode::Save);
+                let status_cols = (rect.width as usize).saturating_sub(2 * (BUTTON_W as usize + 8) + 30) / 6;
+                text(
+                    rect.x + 10,
+                    rect.y + rect.height as i32 - 28,
+                    trim_to(&browser.status, status_cols),
+                    0xF2C879,
+                );
+            }
+            Body::Print {
+                printers,
+                selected,
+                copies,
+            } => {
+                text(rect.x + 10, rect.y + 28, "Impresora:", 0xA6C7E1);
+                self.draw_printer_list(rect, printers, *selected, pointer);
+                let minus = Self::copies_rect(rect, false);
+                let plus = Self::copies_rect(rect, true);
+                text(rect.x + 10, minus.y + 7, "Copias:", 0xA6C7E1);
+                for (button, label) in [(minus, "-"), (plus, "+")] {
+                    fill(button, if button.contains(pointer) { 0x36596F } else { 0x294259 });
+                    text(button.x + 7, button.y + 7, label, 0xEAF6FF);
+                }
+                let count = alloc::format!("{}", copies);
+                text(minus.x + 38 - count.len() as i32 * 3, minus.y + 7, &count, 0xEAF6FF);
+            }
+        }
+
+        let buttons = self.buttons();
+        for (i, label) in buttons.iter().enumerate() {
+            let button = Self::button_rect(rect, buttons.len(), i);
+            let hover = button.contains(pointer);
+            let bg = if hover {
+                0x36596F
+            } else if i == self.focus {
+                0x2E6E9E
+            } else {
+                0x294259
+            };
+            fill(button, bg);
+            fill(Rect::new(button.x, button.y, button.width, 1), 0x8CC8F0);
+            let label = trim_to(label, (BUTTON_W as usize - 8) / 6);
+            let tx = button.x + (BUTTON_W as i32 - label.len() as i32 * 6) / 2;
+            text(tx, button.y + 9, label, 0xEAF6FF);
+        }
+    }
+
+    fn draw_field(&self, rect: Rect, value: &str, editable: bool) {
+        let field = self.field_rect(rect);
+        fill(field, 0x0A1C2B);
+        fill(
+            Rect::new(field.x, field.y + field.height as i32 - 1, field.width, 1),
+            0x4F7A9D,
+        );
+        let cols = (field.width as usize).saturating_sub(16) / 6;
+        // Keep the end of long input in view, where the caret is.
+        let shown = tail_to(value, cols);
+        text(field.x + 6, field.y + (field.height as i32 - 7) / 2, shown, 0xEAF6FF);
+        if editable {
+            let caret_x = field.x + 6 + shown.chars().count() as i32 * 6;
+            fill(Rect::new(caret_x, field.y + 4, 1, field.height - 8), 0x8CC8F0);
+        }
+    }
+
+    fn draw_printer_list(&self, rect: Rect, printers: &[String], selected: usize, pointer: Point) {
+        let list = Self::list_rect(rect);
+        fill(list, 0x0A1C2B);
+        fill(Rect::new(list.x, list.y, list.width, 1), 0x4F7A9D);
+        let cols = (list.width as usize).saturating_sub(16) / 6;
+        for (row, name) in printers.iter().enumerate().take(Self::visible_rows(rect)) {
+            let row_rect = Self::row_rect(rect, row);
+            let bg = if row == selected {
+                0x1A4F78
+            } else if row_rect.contains(pointer) {
+                0x17364F
+            } else {
+                0x10273A
+            };
+            fill(row_rect, bg);
+            text(row_rect.x + 6, row_rect.y + 6, trim_to(name, cols), 0xEAF6FF);
+        }
+        if printers.is_empty() {
+            text(list.x + 8, list.y + 8, "(sin impresoras)", 0x7C8FA6);
+        }
+    }
+
+    fn draw_file_list(&self, rect: Rect, browser: &FileBrowser, pointer: Point) {
+        let list = Self::list_rect(rect);
+        fill(list, 0x0A1C2B);
+        fill(Rect::new(list.x, list.y, list.width, 1), 0x4F7A9D);
+        let cols = (list.width as usize).saturating_sub(96) / 6;
+        for visible_row in 0..Self::visible_rows(rect) {
+            let row = browser.scroll + visible_row;
+            if row >= browser.row_count() {
+                break;
+            }
+            let row_rect = Self::row_rect(rect, visible_row);
+            let bg = if row == browser.selected {
+                0x1A4F78
+            } else if row_rect.contains(pointer) {
+                0x17364F
+            } else {
+                0x10273A
+            };
+            fill(row_rect, bg);
+            let (label, size) = match browser.entry_at_row(row) {
+                Some(entry) if entry.is_directory => (alloc::format!("[{}]", entry.name), String::new()),
+                Some(entry) => (entry.name.clone(), alloc::format!("{} B", entry.size)),
+                None => (String::from("[..]"), String::new()),
+            };
+            text(row_rect.x + 6, row_rect.y + 6, trim_to(&label, cols), 0xEAF6FF);
+            let size_x = row_rect.x + row_rect.width as i32 - 8 - size.len() as i32 * 6;
+            text(size_x, row_rect.y + 6, &size, 0x8FB3CF);
+        }
+        if browser.row_count() == 0 {
+            text(list.x + 8, list.y + 8, "(carpeta vacia)", 0x7C8FA6);
+        }
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "dialog";
+
+    fn message_keys_move_focus_and_press() {
+        let bounds = Rect::new(0, 0, 800, 560);
+        let mut dialog = Dialog::message("Aviso", "Hola", &["Si", "No", "Quizas"]);
+        crate::selftest::ensure_eq(dialog.key(None, Some(SpecialKey::Right), bounds), DialogOutcome::None, "mover")?;
+        crate::selftest::ensure_eq(dialog.key(Some('\n'), None, bounds), DialogOutcome::Button(1), "enter")?;
+        let mut confirm = Dialog::confirm("Borrar", "Seguro?", "Borrar");
+        crate::selftest::ensure_eq(confirm.key(Some('\r'), None, bounds), DialogOutcome::Button(1), "cancelar por defecto")?;
+        crate::selftest::ensure_eq(confirm.key(Some('\x1b'), None, bounds), DialogOutcome::Cancel, "escape")
+    }
+
+    fn prompt_edits_value() {
+        let bounds = Rect::new(0, 0, 800, 560);
+        let mut dialog = Dialog::prompt("Nombre", "Archivo:", "a", 3);
+        for ch in ['b', 'c', 'd'] {
+            dialog.key(Some(ch), None, bounds);
+        }
+        dialog.key(Some('\x08'), None, bounds);
+        crate::selftest::ensure_eq(dialog.key(Some('\n'), None, bounds), DialogOutcome::Text(String::from("ab")), "texto")
+    }
+
+    fn file_dialog_filters_browses_and_names() {
+        let bounds = Rect::new(0, 0, 800, 560);
+        let entry = |name: &str, cluster: u32, is_directory: bool| FileEntry {
+            name: String::from(name),
+            cluster,
+            size: 7,
+            is_directory,
+        };
+        let mut dialog = Dialog::file("Guardar", FileDialogMode::Save, None, 2, "/", "", Some("BMP"));
+        let browser = dialog.file_browser_mut().ok_or("sin navegador")?;
+        browser.set_listing(2, "/", alloc::vec![entry("b.bmp", 9, false), entry("NOTA.TXT", 8, false), entry("IMAGES", 5, true), entry(".", 2, true)]);
+        crate::selftest::ensure_eq(browser.row_count(), 2, "filtro")?;
+        crate::selftest::ensure_eq(browser.entry_at_row(0).map(|e| e.is_directory), Some(true), "carpetas primero")?;
+        crate::selftest::ensure_eq(
+            browser.activate_selected(),
+            DialogOutcome::Browse { cluster: 5, path: String::from("/IMAGES/") },
+            "entrar",
+        )?;
+        browser.set_listing(5, "/IMAGES/", alloc::vec![entry("CAP1.BMP", 11, false)]);
+        crate::selftest::ensure_eq(browser.activate_selected(), DialogOutcome::Browse { cluster: 2, path: String::from("/") }, "subir")?;
+        browser.name = String::from("cap1");
+        match browser.choose_name() {
+            DialogOutcome::File(choice) => {
+                crate::selftest::ensure_eq(choice.name.as_str(), "cap1.BMP", "extension")?;
+                crate::selftest::ensure(choice.exists && choice.cluster == 11, "existente")?;
+            }
+            other => return Err(alloc::format!("resultado inesperado: {:?}", other)),
+        }
+        browser.set_listing(0, "/DATA/", alloc::vec![entry("SUB", 0, true)]);
+        browser.selected = 1;
+        crate::selftest::ensure_eq(
+            browser.activate_selected(),
+            DialogOutcome::Browse { cluster: 0, path: String::from("/DATA/SUB/") },
+            "entrar en montaje",
+        )?;
+        browser.set_listing(0, "/DATA/SUB/", Vec::new());
+        crate::selftest::ensure_eq(browser.activate_selected(), DialogOutcome::Browse { cluster: 0, path: String::from("/DATA/") }, "subir en montaje")?;
+        browser.set_listing(2, "/", Vec::new());
+        crate::selftest::ensure(!browser.has_parent(), "pila vacia")?;
+        browser.name = String::from("a/b");
+        crate::selftest::ensure_eq(dialog.key(Some('\n'), None, bounds), DialogOutcome::None, "nombre invalido")
+    }
+
+    fn print_dialog_picks_printer_and_copies() {
+        let bounds = Rect::new(0, 0, 800, 560);
+        let printers = alloc::vec![String::from("Oficina"), String::from("Casa")];
+        let mut dialog = Dialog::print("Imprimir", printers, 5);
+        dialog.key(Some('+'), None, bounds);
+        dialog.key(Some('+'), None, bounds);
+        dialog.key(Some('-'), None, bounds);
+        crate::selftest::ensure_eq(
+            dialog.key(Some('\n'), None, bounds),
+            DialogOutcome::Print { printer: 1, copies: 2 },
+            "ultima impresora, dos copias",
+        )?;
+        dialog.key(None, Some(SpecialKey::Up), bounds);
+        let rect = dialog.rect(bounds);
+        let first = Dialog::row_rect(rect, 0);
+        crate::selftest::ensure_eq(
+            dialog.click(Point { x: first.x + 4, y: first.y + 4 }, bounds, true),
+            DialogOutcome::Print { printer: 0, copies: 2 },
+            "doble clic imprime",
+        )?;
+        let mut empty = Dialog::print("Imprimir", Vec::new(), 0);
+        crate::selftest::ensure_eq(empty.key(Some('\n'), None, bounds), DialogOutcome::Cancel, "sin impresoras")
+    }
+}
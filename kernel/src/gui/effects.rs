@@ -0,0 +1,292 @@
+//! Window effects: drop shadows, per-window opacity and the open, minimize
+//! and restore animations.
+//!
+//! An [`Animation`] tweens a window's rect and opacity over a fixed time on
+//! the monotonic clock (`perf::now_us`), so it takes as long at 15 FPS as at
+//! 144; slow frames only show fewer steps. While one runs the compositor
+//! draws the window stretched into the tweened rect and keeps asking for
+//! frames.
+//!
+//! Everything here blends with what is already drawn, which on a GOP-only
+//! machine means reading the framebuffer back. `desktop.effects` is `auto`
+//! (effects only with the backbuffer), `on` or `off`; with effects off
+//! windows are drawn opaque and appear and disappear at once.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::Rect;
+use crate::config::ConfigValue;
+
+pub const EFFECTS_KEY: &str = "desktop.effects";
+
+pub const OPEN_US: u64 = 160_000;
+pub const MINIMIZE_US: u64 = 220_000;
+pub const RESTORE_US: u64 = 220_000;
+/// Opening windows grow from this share of their size, in 1/1024.
+const OPEN_START_SCALE: u32 = 920;
+
+/// Lowest opacity `effects opacity` accepts, so a window can't be lost.
+pub const MIN_OPACITY: u8 = 20;
+
+const SHADOW_SIZE: usize = 6;
+/// Darkness of the shadow line next to the window; it fades outwards.
+const SHADOW_ALPHA: u32 = 84;
+
+/// Fixed-point progress: 0 is the start, `ONE` the end.
+const ONE: u32 = 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Setting {
+    Auto,
+    On,
+    Off,
+}
+
+impl Setting {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "auto" => Some(Setting::Auto),
+            "on" | "true" | "1" => Some(Setting::On),
+            "off" | "false" | "0" => Some(Setting::Off),
+            _ => None,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Setting::Auto => "auto",
+            Setting::On => "on",
+            Setting::Off => "off",
+        }
+    }
+}
+
+pub fn setting() -> Setting {
+    match crate::config::get(EFFECTS_KEY) {
+        Some(ConfigValue::Str(text)) => Setting::parse(text.as_str()).unwrap_or(Setting::Auto),
+        Some(ConfigValue::Bool(on)) => {
+            if on {
+                Setting::On
+            } else {
+                Setting::Off
+            }
+        }
+        _ => Setting::Auto,
+    }
+}
+
+pub fn enabled() -> bool {
+    match setting() {
+        Setting::Auto => crate::framebuffer::backbuffer_enabled(),
+        Setting::On => true,
+        Setting::Off => false,
+    }
+}
+
+/// Opacity in percent to the alpha the framebuffer blends with.
+pub fn alpha(opacity: u8) -> u8 {
+    (opacity.min(100) as u32 * 255 / 100) as u8
+}
+
+/// Cubic ease-out: quick start, gentle landing.
+pub fn ease_out(t: u32) -> u32 {
+    let rest = (ONE - t.min(ONE)) as u64;
+    ONE - (rest * rest * rest / (ONE as u64 * ONE as u64)) as u32
+}
+
+fn lerp(from: i64, to: i64, t: u32) -> i64 {
+    from + (to - from) * t as i64 / ONE as i64
+}
+
+pub fn lerp_rect(from: Rect, to: Rect, t: u32) -> Rect {
+    Rect::new(
+        lerp(from.x as i64, to.x as i64, t) as i32,
+        lerp(from.y as i64, to.y as i64, t) as i32,
+        lerp(from.width as i64, to.width as i64, t).max(1) as u32,
+        lerp(from.height as i64, to.height as i64, t).max(1) as u32,
+    )
+}
+
+/// `rect` shrunk to `scale`/1024 around its centre.
+fn scaled_about_center(rect: Rect, scale: u32) -> Rect {
+    let width = (rect.width as u64 * scale as u64 / ONE as u64).max(1) as u32;
+    let height = (rect.height as u64 * scale as u64 / ONE as u64).max(1) as u32;
+    Rect::new(
+        rect.x + (rect.width - width) as i32 / 2,
+        rect.y + (rect.height - height) as i32 / 2,
+        width,
+        height,
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    Open,
+    Minimize,
+    Restore,
+}
+
+/// Where and how opaque to draw an animated window this frame.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Frame {
+    pub rect: Rect,
+    pub alpha: u8,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Animation {
+    pub win_id: usize,
+    pub kind: Kind,
+    from: Frame,
+    to: Frame,
+    start_us: u64,
+    duration_us: u64,
+}
+
+impl Animation {
+    /// `rect` is the window; `tab` its taskbar button when minimized.
+    pub fn new(win_id: usize, kind: Kind, rect: Rect, tab: Rect, alpha: u8, now_us: u64) -> Self {
+        let shown = Frame { rect, alpha };
+        let (from, to, duration_us) = match kind {
+            Kind::Open => (
+                Frame {
+                    rect: scaled_about_center(rect, OPEN_START_SCALE),
+                    alpha: 0,
+                },
+                shown,
+                OPEN_US,
+            ),
+            Kind::Minimize => (shown, Frame { rect: tab, alpha: 0 }, MINIMIZE_US),
+            Kind::Restore => (Frame { rect: tab, alpha: 0 }, shown, RESTORE_US),
+        };
+        Self {
+            win_id,
+            kind,
+            from,
+            to,
+            start_us: now_us,
+            duration_us,
+        }
+    }
+
+    fn progress(&self, now_us: u64) -> u32 {
+        let elapsed = now_us.saturating_sub(self.start_us).min(self.duration_us);
+        (elapsed * ONE as u64 / self.duration_us.max(1)) as u32
+    }
+
+    pub fn frame(&self, now_us: u64) -> Frame {
+        let t = ease_out(self.progress(now_us));
+        Frame {
+            rect: lerp_rect(self.from.rect, self.to.rect, t),
+            alpha: lerp(self.from.alpha as i64, self.to.alpha as i64, t) as u8,
+        }
+    }
+
+    pub fn finished(&self, now_us: u64) -> bool {
+        now_us.saturating_sub(self.start_us) >= self.duration_us
+    }
+}
+
+/// Soft shadow along the right and bottom edges of `rect`.
+pub fn draw_shadow(rect: Rect) {
+    if rect.x < 0 || rect.y < 0 {
+        return;
+    }
+    let (x, y) = (rect.x as usize, rect.y as usize);
+    let (w, h) = (rect.width as usize, rect.height as usize);
+    for layer in 0..SHADOW_SIZE {
+        let alpha = (SHADOW_ALPHA * (SHADOW_SIZE - layer) as u32 / SHADOW_SIZE as u32) as u8;
+        // Each ring starts one pixel further in so the corners round off.
+        let inset = layer + 2;
+        crate::framebuffer::blend_rect(x + w + layer, y + inset, 1, h.saturating_sub(inset) + layer, 0, alpha);
+        crate::framebuffer::blend_rect(x + inset, y + h + layer, w.saturating_sub(inset) + layer, 1, 0, alpha);
+    }
+}
+
+fn status_lines() -> Vec<String> {
+    let setting = setting();
+    alloc::vec![
+        alloc::format!(
+            "Efectos: {} ({})",
+            setting.name(),
+            if enabled() { "activos" } else { "desactivados" }
+        ),
+        String::from("Uso: effects [on|off|auto] | effects opacity <20-100> [titulo]"),
+    ]
+}
+
+/// Shared implementation of `effects [on|off|auto]`. `effects opacity` needs
+/// the windows and is handled by the compositor.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim();
+    if args.is_empty() || args == "status" {
+        return status_lines();
+    }
+    let Some(wanted) = Setting::parse(args) else {
+        return alloc::vec![String::from(
+            "Uso: effects [on|off|auto] | effects opacity <20-100> [titulo]"
+        )];
+    };
+    let result = match wanted {
+        Setting::Auto => crate::config::unset(EFFECTS_KEY).map(|_| ()),
+        _ => crate::config::set(EFFECTS_KEY, ConfigValue::Str(String::from(wanted.name()))),
+    };
+    match result {
+        Ok(()) => status_lines(),
+        Err(e) => alloc::vec![alloc::format!("config: {}", e)],
+    }
+}
+
+/// `<percent> [title]` of `effects opacity`.
+pub fn parse_opacity_args(args: &str) -> Option<(u8, &str)> {
+    let args = args.trim();
+    let (value, title) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let percent = value.trim_end_matches('%').parse::<u8>().ok()?;
+    (MIN_OPACITY..=100)
+        .contains(&percent)
+        .then_some((percent, title.trim()))
+}
+
+crate::selftest::kernel_tests! {
+    "effects";
+
+    fn easing_and_tweens_hit_their_ends() {
+        crate::selftest::ensure_eq(ease_out(0), 0, "inicio")?;
+        crate::selftest::ensure_eq(ease_out(ONE), ONE, "final")?;
+        crate::selftest::ensure(ease_out(ONE / 2) > ONE / 2, "rapido al principio")?;
+        let window = Rect::new(100, 80, 400, 300);
+        let tab = Rect::new(10, 700, 120, 24);
+        let anim = Animation::new(7, Kind::Minimize, window, tab, 255, 1_000);
+        crate::selftest::ensure_eq(anim.frame(1_000), Frame { rect: window, alpha: 255 }, "primer cuadro")?;
+        crate::selftest::ensure_eq(anim.frame(1_000 + MINIMIZE_US * 3), Frame { rect: tab, alpha: 0 }, "ultimo")?;
+        crate::selftest::ensure(!anim.finished(1_000 + MINIMIZE_US / 2), "a medias")?;
+        crate::selftest::ensure(anim.finished(1_000 + MINIMIZE_US), "terminada")?;
+        let mid = anim.frame(1_000 + MINIMIZE_US / 2).rect;
+        crate::selftest::ensure(mid.x < window.x && mid.y > window.y, "camino a la barra")
+    }
+
+    fn opening_grows_from_the_centre() {
+        let window = Rect::new(0, 0, 1000, 500);
+        let first = Animation::new(1, Kind::Open, window, window, 204, 0).frame(0);
+        crate::selftest::ensure_eq(first.alpha, 0, "transparente")?;
+        crate::selftest::ensure_eq(first.rect.width, 898, "ancho")?;
+        crate::selftest::ensure_eq(first.rect.x, 51, "centrado")?;
+        crate::selftest::ensure_eq(alpha(80), 204, "opacidad")
+    }
+
+    fn settings_and_opacity_arguments() {
+        crate::selftest::ensure_eq(Setting::parse(" OFF"), Some(Setting::Off), "off")?;
+        crate::selftest::ensure_eq(Setting::parse("quiza"), None, "invalido")?;
+        crate::selftest::ensure_eq(parse_opacity_args("80% Notas"), Some((80, "Notas")), "con titulo")?;
+        crate::selftest::ensure_eq(parse_opacity_args("100"), Some((100, "")), "solo")?;
+        crate::selftest::ensure_eq(parse_opacity_args("5"), None, "demasiado baja")
+    }
+
+    fn blending_mixes_each_channel() {
+        let mix = crate::framebuffer::blend_packed(0x000000, 0xFF8040, 128);
+        crate::selftest::ensure_eq(mix, 0x804020, "mitad")?;
+        crate::selftest::ensure_eq(crate::framebuffer::blend_packed(0x123456, 0xABCDEF, 255), 0xABCDEF, "opaco")?;
+        crate::selftest::ensure_eq(crate::framebuffer::blend_packed(0x123456, 0xABCDEF, 0), 0x123456, "invisible")
+    }
+}
@@ -0,0 +1,444 @@
+//! Desktop input queue.
+//!
+//! `pump` polls the mouse, touch screens, keyboard and gamepads into a fixed ring with a
+//! timestamp per entry. The GUI loop pumps at frame start and the compositor
+//! pumps again after drawing, so a slow paint delays input instead of
+//! dropping it; `Compositor::drain_input_events` hands everything to
+//! `handle_event` at the start of the next frame. Keys from devices that
+//! report releases (virtio-input) repeat here, with the delay and rate from
+//! `input.repeat_delay_ms` / `input.repeat_rate`; firmware keyboards already
+//! repeat on their own.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use super::{Event, KeyboardEvent, MouseEvent, SpecialKey};
+use crate::config::ConfigValue;
+use crate::input::{self, RuntimeInput, RuntimeKey};
+use crate::spinlock::SpinLock;
+
+pub const CAPACITY: usize = 256;
+pub const REPEAT_DELAY_KEY: &str = "input.repeat_delay_ms";
+pub const REPEAT_RATE_KEY: &str = "input.repeat_rate";
+const DEFAULT_REPEAT_DELAY_MS: u32 = 500;
+const DEFAULT_REPEAT_RATE: u32 = 30;
+
+static REPEAT_DELAY_MS: AtomicU32 = AtomicU32::new(DEFAULT_REPEAT_DELAY_MS);
+static REPEAT_RATE: AtomicU32 = AtomicU32::new(DEFAULT_REPEAT_RATE);
+
+#[derive(Clone, Copy, Debug)]
+pub enum EntryKind {
+    Gui(Event),
+    /// F12: show/hide the performance HUD.
+    TogglePerfHud,
+    /// Super+Space: open/close the quick search overlay.
+    QuickSearch,
+    /// Super+N: go to workspace `index` (0-based); with Shift the focused
+    /// window moves there instead.
+    Workspace {
+        index: usize,
+        move_window: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Entry {
+    /// `perf::now_us` when the event was polled.
+    pub at_us: u64,
+    pub kind: EntryKind,
+}
+
+/// Fixed-size FIFO; when full the oldest entry is dropped and counted.
+pub struct Ring {
+    slots: [Option<Entry>; CAPACITY],
+    head: usize,
+    len: usize,
+    dropped: u64,
+}
+
+impl Default for Ring {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ring {
+    pub const fn new() -> Self {
+        Self {
+            slots: [None; CAPACITY],
+            head: 0,
+            len: 0,
+            dropped: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn push(&mut self, entry: Entry) {
+        if self.len == CAPACITY {
+            self.head = (self.head + 1) % CAPACITY;
+            self.len -= 1;
+            self.dropped += 1;
+        }
+        self.slots[(self.head + self.len) % CAPACITY] = Some(entry);
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<Entry> {
+        if self.len == 0 {
+            return None;
+        }
+        let entry = self.slots[self.head].take();
+        self.head = (self.head + 1) % CAPACITY;
+        self.len -= 1;
+        entry
+    }
+
+    fn last_mut(&mut self) -> Option<&mut Entry> {
+        if self.len == 0 {
+            return None;
+        }
+        self.slots[(self.head + self.len - 1) % CAPACITY].as_mut()
+    }
+
+    /// Queue a pointer report. A motion-only report right behind another one
+    /// with the same buttons replaces it, so a long paint does not leave a
+    /// backlog of stale positions; button changes and wheel steps are kept.
+    pub fn push_mouse(&mut self, at_us: u64, mouse: MouseEvent) {
+        if mouse.wheel_delta == 0 {
+            if let Some(Entry {
+                at_us: last_at,
+                kind: EntryKind::Gui(Event::Mouse(last)),
+            }) = self.last_mut()
+            {
+                if last.wheel_delta == 0 && last.left_down == mouse.left_down && last.right_down == mouse.right_down {
+                    *last = mouse;
+                    *last_at = at_us;
+                    return;
+                }
+            }
+        }
+        self.push(Entry {
+            at_us,
+            kind: EntryKind::Gui(Event::Mouse(mouse)),
+        });
+    }
+
+    fn has_mouse(&self) -> bool {
+        (0..self.len).any(|i| {
+            matches!(
+                self.slots[(self.head + i) % CAPACITY],
+                Some(Entry {
+                    kind: EntryKind::Gui(Event::Mouse(_)),
+                    ..
+                })
+            )
+        })
+    }
+}
+
+/// Synthesized repeat for the last key of a device that reports releases.
+#[derive(Default)]
+pub struct KeyRepeat {
+    key: Option<KeyboardEvent>,
+    next_us: u64,
+}
+
+impl KeyRepeat {
+    pub fn press(&mut self, key: KeyboardEvent, now_us: u64, delay_us: u64) {
+        self.key = Some(key);
+        self.next_us = now_us.saturating_add(delay_us);
+    }
+
+    pub fn clear(&mut self) {
+        self.key = None;
+    }
+
+    /// The repeated key when one is due. Missed repeats while the loop was
+    /// busy collapse into a single one rather than a burst.
+    pub fn due(&mut self, now_us: u64, held: bool, interval_us: u64) -> Option<KeyboardEvent> {
+        if !held {
+            self.key = None;
+            return None;
+        }
+        let key = self.key?;
+        if now_us < self.next_us {
+            return None;
+        }
+        self.next_us = self
+            .next_us
+            .saturating_add(interval_us)
+            .max(now_us.saturating_add(interval_us / 2));
+        Some(key)
+    }
+}
+
+struct Queue {
+    ring: Ring,
+    repeat: KeyRepeat,
+    cursor: (i32, i32),
+    mouse_activity: bool,
+}
+
+static QUEUE: SpinLock<Queue> = SpinLock::new(Queue {
+    ring: Ring::new(),
+    repeat: KeyRepeat { key: None, next_us: 0 },
+    cursor: (0, 0),
+    mouse_activity: false,
+});
+
+fn key_event(key: Option<char>, special: Option<SpecialKey>) -> Option<EntryKind> {
+    Some(EntryKind::Gui(Event::Keyboard(KeyboardEvent {
+        key,
+        special,
+        down: true,
+    })))
+}
+
+fn keyboard_entry(input: RuntimeInput) -> Option<EntryKind> {
+    match input {
+        RuntimeInput::Char(ch) => key_event(Some(ch), None),
+        RuntimeInput::Enter => key_event(Some('\n'), None),
+        RuntimeInput::Backspace => key_event(Some('\x08'), None),
+        RuntimeInput::Key(RuntimeKey::Esc) => key_event(Some('\x1b'), None),
+        RuntimeInput::Key(RuntimeKey::Up) => key_event(None, Some(SpecialKey::Up)),
+        RuntimeInput::Key(RuntimeKey::Down) => key_event(None, Some(SpecialKey::Down)),
+        RuntimeInput::Key(RuntimeKey::Left) => key_event(None, Some(SpecialKey::Left)),
+        RuntimeInput::Key(RuntimeKey::Right) => key_event(None, Some(SpecialKey::Right)),
+        RuntimeInput::Key(RuntimeKey::F12) => Some(EntryKind::TogglePerfHud),
+        RuntimeInput::Key(RuntimeKey::SuperSpace) => Some(EntryKind::QuickSearch),
+        RuntimeInput::Key(RuntimeKey::SuperDigit { digit, shift }) => Some(EntryKind::Workspace {
+            index: digit.saturating_sub(1) as usize,
+            move_window: shift,
+        }),
+        _ => None,
+    }
+}
+
+fn repeat_timing_us() -> (u64, u64) {
+    let delay = REPEAT_DELAY_MS.load(Ordering::Relaxed) as u64 * 1000;
+    let interval = 1_000_000 / REPEAT_RATE.load(Ordering::Relaxed).max(1) as u64;
+    (delay, interval)
+}
+
+/// Poll every input source into the queue.
+pub fn pump() {
+    let (screen_w, screen_h) = crate::framebuffer::dimensions();
+
+    while let Some((dx, dy, wheel_delta, left_down, right_down)) = input::poll_mouse_uefi() {
+        let now = crate::perf::now_us();
+        let mut queue = QUEUE.lock();
+        let (mut x, mut y) = queue.cursor;
+        x = x.saturating_add(dx);
+        y = y.saturating_add(dy);
+        if let Some((abs_x, abs_y)) = input::take_absolute_pointer() {
+            x = abs_x;
+            y = abs_y;
+        }
+        // Re-read on every pump: a UI scale change shrinks the logical screen.
+        x = x.clamp(0, screen_w as i32 - 1);
+        y = y.clamp(0, screen_h as i32 - 1);
+        queue.cursor = (x, y);
+        queue.mouse_activity |= dx != 0 || dy != 0 || wheel_delta != 0 || left_down || right_down;
+        queue.ring.push_mouse(
+            now,
+            MouseEvent {
+                x,
+                y,
+                left_down,
+                right_down,
+                wheel_delta,
+            },
+        );
+    }
+
+    while let Some(touch) = crate::touch::poll_event() {
+        let now = crate::perf::now_us();
+        let mut queue = QUEUE.lock();
+        let x = touch.x.clamp(0, screen_w as i32 - 1);
+        let y = touch.y.clamp(0, screen_h as i32 - 1);
+        queue.cursor = (x, y);
+        queue.mouse_activity = true;
+        queue.ring.push_mouse(
+            now,
+            MouseEvent {
+                x,
+                y,
+                left_down: touch.left,
+                right_down: touch.right,
+                wheel_delta: touch.wheel,
+            },
+        );
+    }
+
+    let (delay_us, interval_us) = repeat_timing_us();
+    while let Some(raw) = input::poll_input_uefi() {
+        let now = crate::perf::now_us();
+        let origin = input::last_key_repeat();
+        if origin == Some(true) {
+            // Device autorepeat: the queue synthesizes its own at the configured rate.
+            continue;
+        }
+        let Some(kind) = keyboard_entry(raw) else {
+            continue;
+        };
+        super::osk::note_hardware_key();
+        let mut queue = QUEUE.lock();
+        match (origin, kind) {
+            (Some(false), EntryKind::Gui(Event::Keyboard(key))) => queue.repeat.press(key, now, delay_us),
+            _ => queue.repeat.clear(),
+        }
+        queue.ring.push(Entry { at_us: now, kind });
+    }
+    {
+        let now = crate::perf::now_us();
+        let mut queue = QUEUE.lock();
+        if let Some(key) = queue.repeat.due(now, input::virtio_key_held(), interval_us) {
+            queue.ring.push(Entry {
+                at_us: now,
+                kind: EntryKind::Gui(Event::Keyboard(key)),
+            });
+        }
+    }
+
+    while let Some(pad_event) = crate::gamepad::poll_event() {
+        let now = crate::perf::now_us();
+        QUEUE.lock().ring.push(Entry {
+            at_us: now,
+            kind: EntryKind::Gui(Event::Gamepad(pad_event)),
+        });
+    }
+}
+
+/// Queue a key that did not come from a device (the on-screen keyboard).
+/// It takes the same path as a real key press.
+pub fn push_synthetic(key: KeyboardEvent) {
+    let now = crate::perf::now_us();
+    QUEUE.lock().ring.push(Entry {
+        at_us: now,
+        kind: EntryKind::Gui(Event::Keyboard(key)),
+    });
+}
+
+/// Whether the pointer moved, clicked or scrolled since the last call.
+pub fn take_mouse_activity() -> bool {
+    core::mem::take(&mut QUEUE.lock().mouse_activity)
+}
+
+pub fn pop() -> Option<Entry> {
+    QUEUE.lock().ring.pop()
+}
+
+/// Take the compositor's pointer position as the base for the next deltas,
+/// unless reports are still queued (those already carry newer positions).
+pub fn sync_cursor(x: i32, y: i32) {
+    let mut queue = QUEUE.lock();
+    if !queue.ring.has_mouse() {
+        queue.cursor = (x, y);
+    }
+}
+
+/// (queued, dropped since boot)
+pub fn stats() -> (usize, u64) {
+    let queue = QUEUE.lock();
+    (queue.ring.len(), queue.ring.dropped())
+}
+
+fn config_u32(value: Option<&ConfigValue>, default: u32, min: u32, max: u32) -> u32 {
+    match value {
+        Some(ConfigValue::Int(n)) if *n >= min as i64 && *n <= max as i64 => *n as u32,
+        _ => default,
+    }
+}
+
+fn on_repeat_changed(key: &str, value: Option<&ConfigValue>) {
+    if key == REPEAT_DELAY_KEY {
+        REPEAT_DELAY_MS.store(config_u32(value, DEFAULT_REPEAT_DELAY_MS, 100, 2000), Ordering::Relaxed);
+    } else if key == REPEAT_RATE_KEY {
+        REPEAT_RATE.store(config_u32(value, DEFAULT_REPEAT_RATE, 1, 100), Ordering::Relaxed);
+    }
+}
+
+/// Follow the repeat keys in the config registry.
+pub fn init() {
+    crate::config::watch("input.repeat", on_repeat_changed);
+}
+
+/// Desktop entry: start the cursor at `(x, y)` and pick up the stored
+/// repeat settings.
+pub fn start_desktop(x: i32, y: i32) {
+    QUEUE.lock().cursor = (x, y);
+    on_repeat_changed(REPEAT_DELAY_KEY, crate::config::get(REPEAT_DELAY_KEY).as_ref());
+    on_repeat_changed(REPEAT_RATE_KEY, crate::config::get(REPEAT_RATE_KEY).as_ref());
+}
+
+crate::selftest::kernel_tests! {
+    "event_queue";
+
+    fn ring_drops_oldest_when_full() {
+        let mut ring = Ring::new();
+        for i in 0..(CAPACITY as u64 + 3) {
+            ring.push(Entry { at_us: i, kind: EntryKind::TogglePerfHud });
+        }
+        crate::selftest::ensure_eq(ring.len(), CAPACITY, "lleno")?;
+        crate::selftest::ensure_eq(ring.dropped(), 3, "descartados")?;
+        crate::selftest::ensure_eq(ring.pop().map(|e| e.at_us), Some(3), "mas antiguo")?;
+        let mut last = 0;
+        while let Some(entry) = ring.pop() {
+            last = entry.at_us;
+        }
+        crate::selftest::ensure_eq(last, CAPACITY as u64 + 2, "orden FIFO")?;
+        crate::selftest::ensure(ring.is_empty(), "vacio")
+    }
+
+    fn motion_coalesces_but_clicks_survive() {
+        let mouse = |x, left_down| MouseEvent { x, y: 0, left_down, right_down: false, wheel_delta: 0 };
+        let mut ring = Ring::new();
+        ring.push_mouse(1, mouse(1, false));
+        ring.push_mouse(2, mouse(2, false));
+        ring.push_mouse(3, mouse(3, true));
+        ring.push_mouse(4, mouse(4, true));
+        ring.push_mouse(5, mouse(5, false));
+        crate::selftest::ensure_eq(ring.len(), 3, "movimiento fusionado")?;
+        let mut seen = alloc::vec::Vec::new();
+        while let Some(Entry { at_us, kind: EntryKind::Gui(Event::Mouse(m)) }) = ring.pop() {
+            seen.push((at_us, m.x, m.left_down));
+        }
+        crate::selftest::ensure_eq(seen, alloc::vec![(2, 2, false), (4, 4, true), (5, 5, false)], "pulsacion")
+    }
+
+    fn repeat_waits_for_delay_then_follows_rate() {
+        let key = KeyboardEvent { key: Some('a'), special: None, down: true };
+        let mut repeat = KeyRepeat::default();
+        repeat.press(key, 0, 500_000);
+        crate::selftest::ensure(repeat.due(400_000, true, 50_000).is_none(), "antes del retardo")?;
+        crate::selftest::ensure(repeat.due(500_000, true, 50_000).is_some(), "primera repeticion")?;
+        crate::selftest::ensure(repeat.due(520_000, true, 50_000).is_none(), "entre repeticiones")?;
+        crate::selftest::ensure(repeat.due(550_000, true, 50_000).is_some(), "segunda repeticion")?;
+        crate::selftest::ensure(repeat.due(2_000_000, true, 50_000).is_some(), "tras una pausa")?;
+        crate::selftest::ensure(repeat.due(2_010_000, true, 50_000).is_none(), "sin rafaga")?;
+        crate::selftest::ensure(repeat.due(3_000_000, false, 50_000).is_none(), "soltada")?;
+        crate::selftest::ensure(repeat.due(3_100_000, true, 50_000).is_none(), "olvidada")
+    }
+
+    fn super_digits_pick_a_workspace() {
+        let entry = |digit, shift| keyboard_entry(RuntimeInput::Key(RuntimeKey::SuperDigit { digit, shift }));
+        crate::selftest::ensure(
+            matches!(entry(1, false), Some(EntryKind::Workspace { index: 0, move_window: false })),
+            "Super+1",
+        )?;
+        crate::selftest::ensure(
+            matches!(entry(4, true), Some(EntryKind::Workspace { index: 3, move_window: true })),
+            "Super+Shift+4",
+        )
+    }
+}
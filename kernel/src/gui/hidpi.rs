@@ -0,0 +1,114 @@
+//! Desktop UI scale (1x, 1.5x, 2x).
+//!
+//! The compositor keeps drawing in logical pixels; `framebuffer` owns the
+//! upscale to the panel and `blit_native` for surfaces with more detail than
+//! their logical rect. This module maps the `desktop.scale` config key to a
+//! percentage and implements the `scale` command.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::config::ConfigValue;
+use crate::framebuffer;
+
+pub const SCALE_KEY: &str = "desktop.scale";
+
+/// "1x", "1.5x", "2x", "150%" or a bare percentage / multiplier.
+pub fn parse_scale(text: &str) -> Option<u32> {
+    let text = text.trim().to_ascii_lowercase();
+    let pct = if let Some(factor) = text.strip_suffix('x') {
+        match factor {
+            "1" | "1.0" => 100,
+            "1.5" => 150,
+            "2" | "2.0" => 200,
+            _ => return None,
+        }
+    } else {
+        let digits = text.strip_suffix('%').unwrap_or(text.as_str());
+        match digits.parse::<u32>().ok()? {
+            n @ 1..=2 => n * 100,
+            n => n,
+        }
+    };
+    framebuffer::SCALE_STEPS.contains(&pct).then_some(pct)
+}
+
+pub fn format_scale(pct: u32) -> String {
+    match pct {
+        150 => String::from("1.5x"),
+        _ => alloc::format!("{}x", pct / 100),
+    }
+}
+
+/// Scale stored in the config registry; 100 when unset or invalid.
+pub fn configured_percent() -> u32 {
+    match crate::config::get(SCALE_KEY) {
+        Some(ConfigValue::Int(n)) => parse_scale(alloc::format!("{}", n).as_str()),
+        Some(ConfigValue::Str(text)) => parse_scale(text.as_str()),
+        _ => None,
+    }
+    .unwrap_or(100)
+}
+
+/// Shared implementation of `scale [1x|1.5x|2x|status]`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim();
+    let mut out = Vec::new();
+    if args.is_empty() || args == "status" {
+        let pct = framebuffer::scale_percent();
+        let (w, h) = framebuffer::dimensions();
+        let (pw, ph) = framebuffer::physical_dimensions();
+        out.push(alloc::format!(
+            "Escala de interfaz: {} ({}%), escritorio {}x{} sobre panel {}x{}",
+            format_scale(pct),
+            pct,
+            w,
+            h,
+            pw,
+            ph
+        ));
+        let configured = configured_percent();
+        if configured != pct {
+            out.push(alloc::format!(
+                "Configurada: {} (se aplica en el escritorio)",
+                format_scale(configured)
+            ));
+        }
+        out.push(String::from("Opciones: 1x, 1.5x, 2x"));
+        return out;
+    }
+    let Some(pct) = parse_scale(args) else {
+        out.push(alloc::format!("scale: valor invalido '{}' (1x, 1.5x, 2x)", args));
+        return out;
+    };
+    match crate::config::set(SCALE_KEY, ConfigValue::Str(format_scale(pct))) {
+        Ok(()) => out.push(alloc::format!("Escala de interfaz: {}.", format_scale(pct))),
+        Err(e) => out.push(alloc::format!("scale: {}", e)),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "hidpi";
+
+    fn parse_scale_accepts_factors_and_percentages() {
+        crate::selftest::ensure_eq(parse_scale("1x"), Some(100), "1x")?;
+        crate::selftest::ensure_eq(parse_scale("1.5X"), Some(150), "1.5x")?;
+        crate::selftest::ensure_eq(parse_scale("200%"), Some(200), "200%")?;
+        crate::selftest::ensure_eq(parse_scale("2"), Some(200), "2")?;
+        crate::selftest::ensure_eq(parse_scale("125"), None, "125")?;
+        crate::selftest::ensure_eq(parse_scale("3x"), None, "3x")
+    }
+
+    fn format_round_trips() {
+        for pct in framebuffer::SCALE_STEPS {
+            crate::selftest::ensure_eq(parse_scale(format_scale(pct).as_str()), Some(pct), "ida y vuelta")?;
+        }
+        Ok(())
+    }
+
+    fn logical_size_for_4k() {
+        crate::selftest::ensure_eq(framebuffer::scaled_size(3840, 2160, 200), (1920, 1080), "2x")?;
+        crate::selftest::ensure_eq(framebuffer::scaled_size(3840, 2160, 150), (2560, 1440), "1.5x")
+    }
+}
@@ -0,0 +1,345 @@
+//! Pointer interaction shared by every window: click counting with the
+//! configurable double-click interval, the drag-and-drop session and
+//! generic right-click context menus.
+//!
+//! Drag and drop: a window offers a `clipboard::Payload` on press with
+//! `Compositor::offer_drag`; the session starts once the pointer travels
+//! `DRAG_THRESHOLD` pixels with the button held. The source gets `Start`,
+//! windows that accept the payload kind get `Over`/`Leave` as the pointer
+//! crosses them, and the window under the pointer on release gets `Drop`
+//! (the payload is handed over with it). Events are also queued on the
+//! windows (`Window::take_drag_events`) for apps that draw their own
+//! feedback.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use super::clipboard::{Payload, PayloadKind};
+use super::{Point, Rect};
+use crate::config::ConfigValue;
+use crate::framebuffer;
+
+pub const DOUBLE_CLICK_KEY: &str = "desktop.double_click_ms";
+pub const DOUBLE_CLICK_DEFAULT_MS: u32 = 500;
+pub const DOUBLE_CLICK_MIN_MS: u32 = 150;
+pub const DOUBLE_CLICK_MAX_MS: u32 = 2000;
+/// Presses closer than this are contact bounce, not a second click.
+const DOUBLE_CLICK_DEBOUNCE_US: u64 = 30_000;
+/// Pixels the second click may land away from the first.
+const CLICK_SLOP: i32 = 4;
+/// Pixels the pointer travels with the button held before a drag starts.
+pub const DRAG_THRESHOLD: i32 = 6;
+
+static DOUBLE_CLICK_MS: AtomicU32 = AtomicU32::new(DOUBLE_CLICK_DEFAULT_MS);
+
+pub fn double_click_interval_us() -> u64 {
+    DOUBLE_CLICK_MS.load(Ordering::Relaxed) as u64 * 1000
+}
+
+/// Whether two presses `delta_us` apart make a double click.
+pub fn is_double_click(delta_us: u64) -> bool {
+    (DOUBLE_CLICK_DEBOUNCE_US..=double_click_interval_us()).contains(&delta_us)
+}
+
+fn on_double_click_changed(_key: &str, value: Option<&ConfigValue>) {
+    let ms = match value {
+        Some(ConfigValue::Int(n)) => (*n).clamp(DOUBLE_CLICK_MIN_MS as i64, DOUBLE_CLICK_MAX_MS as i64) as u32,
+        _ => DOUBLE_CLICK_DEFAULT_MS,
+    };
+    DOUBLE_CLICK_MS.store(ms, Ordering::Relaxed);
+}
+
+/// Follow `desktop.double_click_ms` in the config registry.
+pub fn init() {
+    crate::config::watch(DOUBLE_CLICK_KEY, on_double_click_changed);
+}
+
+pub fn load_config() {
+    on_double_click_changed(DOUBLE_CLICK_KEY, crate::config::get(DOUBLE_CLICK_KEY).as_ref());
+}
+
+/// Counts consecutive presses at the same spot: 1 single, 2 double, 3 triple.
+#[derive(Clone, Copy, Default)]
+pub struct ClickTracker {
+    last: Option<(Point, u64)>,
+    count: u8,
+}
+
+impl ClickTracker {
+    pub fn press(&mut self, p: Point, at_us: u64, interval_us: u64) -> u8 {
+        let chained = match self.last {
+            Some((prev, prev_at)) => {
+                let delta = at_us.saturating_sub(prev_at);
+                (prev.x - p.x).abs() <= CLICK_SLOP
+                    && (prev.y - p.y).abs() <= CLICK_SLOP
+                    && (DOUBLE_CLICK_DEBOUNCE_US..=interval_us).contains(&delta)
+            }
+            None => false,
+        };
+        self.count = if chained { self.count.saturating_add(1) } else { 1 };
+        self.last = Some((p, at_us));
+        self.count
+    }
+
+    pub fn count(&self) -> u8 {
+        self.count
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DragPhase {
+    Start,
+    Over,
+    Leave,
+    Drop,
+    Cancel,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DragEvent {
+    pub phase: DragPhase,
+    pub kind: PayloadKind,
+    /// Pointer position, screen coordinates.
+    pub x: i32,
+    pub y: i32,
+}
+
+/// What the compositor has to deliver after a pointer update.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DragStep {
+    /// Press not yet a drag; the click handling already ran.
+    Pending,
+    /// Released before the threshold: it was only a click.
+    Released,
+    Started,
+    Moved {
+        left: Option<usize>,
+        entered: Option<usize>,
+    },
+    Dropped {
+        target: Option<usize>,
+    },
+}
+
+pub struct DragSession {
+    pub source_win: usize,
+    pub payload: Payload,
+    origin: Point,
+    active: bool,
+    over_win: Option<usize>,
+}
+
+impl DragSession {
+    pub fn new(source_win: usize, payload: Payload, origin: Point) -> Self {
+        Self {
+            source_win,
+            payload,
+            origin,
+            active: false,
+            over_win: None,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn over_win(&self) -> Option<usize> {
+        self.over_win
+    }
+
+    /// Advance with a pointer report. `target` is the window under the
+    /// pointer that accepts the payload kind, if any.
+    pub fn update(&mut self, p: Point, left_down: bool, target: Option<usize>) -> DragStep {
+        if !self.active {
+            if !left_down {
+                return DragStep::Released;
+            }
+            if (p.x - self.origin.x).abs() < DRAG_THRESHOLD && (p.y - self.origin.y).abs() < DRAG_THRESHOLD {
+                return DragStep::Pending;
+            }
+            self.active = true;
+            return DragStep::Started;
+        }
+        if !left_down {
+            return DragStep::Dropped {
+                target: self.over_win.take().or(target),
+            };
+        }
+        if target == self.over_win {
+            return DragStep::Moved {
+                left: None,
+                entered: None,
+            };
+        }
+        let left = self.over_win;
+        self.over_win = target;
+        DragStep::Moved { left, entered: target }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MenuItem {
+    pub label: String,
+    /// Handed back to the window when the item is chosen.
+    pub action: u32,
+    pub enabled: bool,
+}
+
+impl MenuItem {
+    pub fn new(label: &str, action: u32) -> Self {
+        Self {
+            label: String::from(label),
+            action,
+            enabled: true,
+        }
+    }
+
+    pub fn disabled(label: &str, action: u32) -> Self {
+        Self {
+            label: String::from(label),
+            action,
+            enabled: false,
+        }
+    }
+}
+
+pub const MENU_WIDTH: u32 = 172;
+pub const MENU_ITEM_H: u32 = 24;
+const MENU_PADDING: i32 = 4;
+
+/// Right-click menu owned by a window; the compositor opens, draws and
+/// hit-tests it, and passes the chosen action to the window.
+pub struct ContextMenu {
+    pub win_id: usize,
+    pub items: Vec<MenuItem>,
+    origin: Point,
+}
+
+impl ContextMenu {
+    /// Menu at the pointer, moved inside `bounds` (the desktop above the taskbar).
+    pub fn open(win_id: usize, items: Vec<MenuItem>, at: Point, bounds: Rect) -> Self {
+        let height = Self::height_for(items.len()) as i32;
+        let max_x = (bounds.x + bounds.width as i32 - MENU_WIDTH as i32).max(bounds.x);
+        let max_y = (bounds.y + bounds.height as i32 - height).max(bounds.y);
+        Self {
+            win_id,
+            items,
+            origin: Point {
+                x: at.x.clamp(bounds.x, max_x),
+                y: at.y.clamp(bounds.y, max_y),
+            },
+        }
+    }
+
+    fn height_for(count: usize) -> u32 {
+        count as u32 * MENU_ITEM_H + MENU_PADDING as u32 * 2
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::new(
+            self.origin.x,
+            self.origin.y,
+            MENU_WIDTH,
+            Self::height_for(self.items.len()),
+        )
+    }
+
+    fn item_rect(&self, index: usize) -> Rect {
+        Rect::new(
+            self.origin.x + MENU_PADDING,
+            self.origin.y + MENU_PADDING + index as i32 * MENU_ITEM_H as i32,
+            MENU_WIDTH - MENU_PADDING as u32 * 2,
+            MENU_ITEM_H,
+        )
+    }
+
+    /// Action of the enabled item under `p`.
+    pub fn action_at(&self, p: Point) -> Option<u32> {
+        (0..self.items.len())
+            .find(|&idx| self.item_rect(idx).contains(p))
+            .and_then(|idx| self.items.get(idx))
+            .filter(|item| item.enabled)
+            .map(|item| item.action)
+    }
+
+    pub fn draw(&self, pointer: Point) {
+        let rect = self.rect();
+        let (x, y) = (rect.x.max(0) as usize, rect.y.max(0) as usize);
+        framebuffer::rect(x, y, rect.width as usize, rect.height as usize, 0x1D2D3D);
+        framebuffer::rect(x, y, rect.width as usize, 1, 0x5F85A8);
+        framebuffer::rect(x, y + rect.height as usize - 1, rect.width as usize, 1, 0x0F1A27);
+        for (idx, item) in self.items.iter().enumerate() {
+            let item_rect = self.item_rect(idx);
+            let hover = item.enabled && item_rect.contains(pointer);
+            let bg = if hover { 0x36596F } else { 0x294259 };
+            let fg = if item.enabled { 0xEAF6FF } else { 0x7C8FA6 };
+            framebuffer::rect(
+                item_rect.x.max(0) as usize,
+                item_rect.y.max(0) as usize,
+                item_rect.width as usize,
+                item_rect.height as usize,
+                bg,
+            );
+            framebuffer::draw_text_5x7(
+                (item_rect.x + 8).max(0) as usize,
+                (item_rect.y + 8).max(0) as usize,
+                item.label.as_str(),
+                fg,
+            );
+        }
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "interaction";
+
+    fn clicks_chain_within_interval_and_slop() {
+        let mut clicks = ClickTracker::default();
+        let p = Point { x: 100, y: 100 };
+        crate::selftest::ensure_eq(clicks.press(p, 1_000_000, 500_000), 1, "primero")?;
+        crate::selftest::ensure_eq(clicks.press(Point { x: 102, y: 99 }, 1_300_000, 500_000), 2, "doble")?;
+        crate::selftest::ensure_eq(clicks.press(p, 1_500_000, 500_000), 3, "triple")?;
+        crate::selftest::ensure_eq(clicks.press(p, 2_200_000, 500_000), 1, "fuera de tiempo")?;
+        crate::selftest::ensure_eq(clicks.press(Point { x: 120, y: 100 }, 2_400_000, 500_000), 1, "lejos")?;
+        crate::selftest::ensure_eq(clicks.press(Point { x: 120, y: 100 }, 2_410_000, 500_000), 1, "rebote")
+    }
+
+    fn drag_starts_after_threshold_and_tracks_targets() {
+        let mut drag = DragSession::new(1, Payload::Text(String::from("x")), Point { x: 10, y: 10 });
+        crate::selftest::ensure_eq(drag.update(Point { x: 12, y: 11 }, true, None), DragStep::Pending, "umbral")?;
+        crate::selftest::ensure_eq(drag.update(Point { x: 30, y: 10 }, true, None), DragStep::Started, "inicio")?;
+        crate::selftest::ensure_eq(
+            drag.update(Point { x: 40, y: 10 }, true, Some(2)),
+            DragStep::Moved { left: None, entered: Some(2) },
+            "entra",
+        )?;
+        crate::selftest::ensure_eq(
+            drag.update(Point { x: 41, y: 10 }, true, Some(2)),
+            DragStep::Moved { left: None, entered: None },
+            "sigue encima",
+        )?;
+        crate::selftest::ensure_eq(
+            drag.update(Point { x: 90, y: 10 }, true, Some(3)),
+            DragStep::Moved { left: Some(2), entered: Some(3) },
+            "cambia",
+        )?;
+        crate::selftest::ensure_eq(drag.update(Point { x: 90, y: 10 }, false, Some(3)), DragStep::Dropped { target: Some(3) }, "suelta")?;
+        let mut click = DragSession::new(1, Payload::Text(String::from("x")), Point { x: 10, y: 10 });
+        crate::selftest::ensure_eq(click.update(Point { x: 10, y: 10 }, false, None), DragStep::Released, "solo clic")
+    }
+
+    fn menu_clamps_and_skips_disabled_items() {
+        let bounds = Rect::new(0, 0, 800, 560);
+        let items = alloc::vec![MenuItem::new("Copiar", 1), MenuItem::disabled("Pegar", 2)];
+        let menu = ContextMenu::open(7, items, Point { x: 790, y: 550 }, bounds);
+        let rect = menu.rect();
+        crate::selftest::ensure(rect.x + rect.width as i32 <= 800, "dentro en x")?;
+        crate::selftest::ensure(rect.y + rect.height as i32 <= 560, "dentro en y")?;
+        let first = Point { x: rect.x + 10, y: rect.y + MENU_PADDING + 4 };
+        let second = Point { x: rect.x + 10, y: rect.y + MENU_PADDING + MENU_ITEM_H as i32 + 4 };
+        crate::selftest::ensure_eq(menu.action_at(first), Some(1), "habilitado")?;
+        crate::selftest::ensure_eq(menu.action_at(second), None, "deshabilitado")
+    }
+}
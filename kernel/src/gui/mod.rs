@@ -1,6 +1,18 @@
 pub mod compositor;
 pub mod window;
 pub mod widgets;
+pub mod notifications;
+pub mod hidpi;
+pub mod event_queue;
+pub mod clipboard;
+pub mod interaction;
+pub mod dialog;
+pub mod search_overlay;
+pub mod screenshot;
+pub mod caret;
+pub mod osk;
+pub mod effects;
+pub mod session;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Point {
@@ -42,10 +54,11 @@ impl Color {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug)]
 pub enum Event {
     Mouse(MouseEvent),
     Keyboard(KeyboardEvent),
+    Gamepad(crate::gamepad::GamepadEvent),
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -0,0 +1,283 @@
+//! Desktop notifications.
+//!
+//! Apps and kernel subsystems post short messages here from any context; the
+//! compositor shows the newest ones as toasts above the taskbar and keeps the
+//! history for the notification center behind the bell icon in the tray.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::spinlock::SpinLock;
+
+const HISTORY_MAX: usize = 64;
+const TITLE_MAX_BYTES: usize = 48;
+const BODY_MAX_BYTES: usize = 160;
+/// How long a toast stays on screen.
+pub const TOAST_MS: u64 = 6_000;
+pub const TOAST_MAX_VISIBLE: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Urgency {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Urgency {
+    /// Accent color for toasts and history rows.
+    pub const fn color(self) -> u32 {
+        match self {
+            Urgency::Info => 0x6FA8DC,
+            Urgency::Success => 0x4ADE80,
+            Urgency::Warning => 0xFBBF24,
+            Urgency::Error => 0xF87171,
+        }
+    }
+
+    fn klog_level(self) -> crate::klog::Level {
+        match self {
+            Urgency::Info | Urgency::Success => crate::klog::Level::Info,
+            Urgency::Warning => crate::klog::Level::Warning,
+            Urgency::Error => crate::klog::Level::Error,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Notification {
+    pub id: u64,
+    /// Who posted it ("red", "wifi", "pkg", an app name...).
+    pub source: String,
+    pub title: String,
+    pub body: String,
+    pub urgency: Urgency,
+    /// Uptime when posted; drives toast expiry.
+    pub posted_uptime_ms: u64,
+    /// Wall clock when posted, for the history timestamp.
+    pub posted_unix_ms: i64,
+    pub read: bool,
+    /// Toast closed by the user before it expired.
+    pub toast_dismissed: bool,
+}
+
+fn clip(text: &str, max: usize) -> String {
+    let text = text.trim();
+    let mut end = text.len().min(max);
+    while end > 0 && !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    String::from(&text[..end])
+}
+
+/// Bounded notification history, oldest first.
+pub struct NotificationCenter {
+    items: VecDeque<Notification>,
+    next_id: u64,
+}
+
+impl NotificationCenter {
+    pub const fn new() -> Self {
+        Self {
+            items: VecDeque::new(),
+            next_id: 1,
+        }
+    }
+
+    pub fn push(
+        &mut self,
+        source: &str,
+        title: &str,
+        body: &str,
+        urgency: Urgency,
+        uptime_ms: u64,
+        unix_ms: i64,
+    ) -> u64 {
+        if self.items.len() >= HISTORY_MAX {
+            let _ = self.items.pop_front();
+        }
+        let id = self.next_id;
+        self.next_id = self.next_id.saturating_add(1);
+        self.items.push_back(Notification {
+            id,
+            source: clip(source, TITLE_MAX_BYTES),
+            title: clip(title, TITLE_MAX_BYTES),
+            body: clip(body, BODY_MAX_BYTES),
+            urgency,
+            posted_uptime_ms: uptime_ms,
+            posted_unix_ms: unix_ms,
+            read: false,
+            toast_dismissed: false,
+        });
+        id
+    }
+
+    /// Newest first.
+    pub fn history(&self) -> Vec<Notification> {
+        self.items.iter().rev().cloned().collect()
+    }
+
+    pub fn unread(&self) -> usize {
+        self.items.iter().filter(|n| !n.read).count()
+    }
+
+    pub fn mark_all_read(&mut self) {
+        for item in self.items.iter_mut() {
+            item.read = true;
+        }
+    }
+
+    pub fn dismiss_toast(&mut self, id: u64) {
+        if let Some(item) = self.items.iter_mut().find(|n| n.id == id) {
+            item.toast_dismissed = true;
+        }
+    }
+
+    pub fn remove(&mut self, id: u64) {
+        self.items.retain(|n| n.id != id);
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// Toasts still on screen at `uptime_ms`, newest first.
+    pub fn toasts(&self, uptime_ms: u64) -> Vec<Notification> {
+        self.items
+            .iter()
+            .rev()
+            .filter(|n| !n.toast_dismissed && uptime_ms.saturating_sub(n.posted_uptime_ms) < TOAST_MS)
+            .take(TOAST_MAX_VISIBLE)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for NotificationCenter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static CENTER: SpinLock<NotificationCenter> = SpinLock::new(NotificationCenter::new());
+
+/// Post a notification. Also recorded in the kernel log.
+pub fn post(source: &str, title: &str, body: &str, urgency: Urgency) -> u64 {
+    // The heap is not available until allocator::init_heap runs.
+    if crate::allocator::heap_size_bytes() == 0 {
+        return 0;
+    }
+    crate::klog::log(
+        urgency.klog_level(),
+        alloc::format!("notify [{}] {}: {}", source, title, body).as_str(),
+    );
+    crate::synth::notification_sound(urgency);
+    let uptime_ms = crate::timer::snapshot().uptime_ms;
+    let unix_ms = crate::timer::wall_clock_unix_millis();
+    CENTER.lock().push(source, title, body, urgency, uptime_ms, unix_ms)
+}
+
+pub fn history() -> Vec<Notification> {
+    CENTER.lock().history()
+}
+
+pub fn unread_count() -> usize {
+    CENTER.lock().unread()
+}
+
+pub fn mark_all_read() {
+    CENTER.lock().mark_all_read();
+}
+
+pub fn dismiss_toast(id: u64) {
+    CENTER.lock().dismiss_toast(id);
+}
+
+pub fn remove(id: u64) {
+    CENTER.lock().remove(id);
+}
+
+pub fn clear() {
+    CENTER.lock().clear();
+}
+
+pub fn active_toasts() -> Vec<Notification> {
+    CENTER.lock().toasts(crate::timer::snapshot().uptime_ms)
+}
+
+/// Shared implementation of `notify`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim();
+    let (sub, rest) = args.split_once(' ').unwrap_or((args, ""));
+    let mut out = Vec::new();
+    match sub {
+        "" | "list" => {
+            let items = history();
+            if items.is_empty() {
+                out.push(String::from("Sin notificaciones."));
+            }
+            for item in items.iter() {
+                out.push(alloc::format!(
+                    "{} #{} [{}] {}{}{}",
+                    if item.read { " " } else { "*" },
+                    item.id,
+                    item.source,
+                    item.title,
+                    if item.body.is_empty() { "" } else { " - " },
+                    item.body
+                ));
+            }
+            mark_all_read();
+        }
+        "clear" => {
+            clear();
+            out.push(String::from("Notificaciones borradas."));
+        }
+        "send" | "warn" | "error" => {
+            let rest = rest.trim();
+            if rest.is_empty() {
+                out.push(String::from("Uso: notify send|warn|error <titulo> [| texto]"));
+                return out;
+            }
+            let (title, body) = rest.split_once('|').unwrap_or((rest, ""));
+            let urgency = match sub {
+                "warn" => Urgency::Warning,
+                "error" => Urgency::Error,
+                _ => Urgency::Info,
+            };
+            let id = post("shell", title.trim(), body.trim(), urgency);
+            out.push(alloc::format!("Notificacion #{} enviada.", id));
+        }
+        _ => out.push(String::from("Uso: notify [list|clear|send|warn|error <titulo> [| texto]]")),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "notifications";
+
+    fn history_is_bounded_and_newest_first() {
+        let mut center = NotificationCenter::new();
+        for i in 0..HISTORY_MAX + 5 {
+            center.push("t", alloc::format!("n{}", i).as_str(), "", Urgency::Info, 0, 0);
+        }
+        let items = center.history();
+        crate::selftest::ensure_eq(items.len(), HISTORY_MAX, "limite")?;
+        crate::selftest::ensure_eq(items[0].title.as_str(), "n68", "mas nueva primero")?;
+        crate::selftest::ensure_eq(center.unread(), HISTORY_MAX, "no leidas")?;
+        center.mark_all_read();
+        crate::selftest::ensure_eq(center.unread(), 0, "leidas")
+    }
+
+    fn toasts_expire_and_dismiss() {
+        let mut center = NotificationCenter::new();
+        let a = center.push("t", "a", "", Urgency::Info, 1_000, 0);
+        center.push("t", "b", "", Urgency::Warning, 2_000, 0);
+        crate::selftest::ensure_eq(center.toasts(2_500).len(), 2, "visibles")?;
+        crate::selftest::ensure_eq(center.toasts(1_000 + TOAST_MS).len(), 1, "expirada")?;
+        center.dismiss_toast(a);
+        crate::selftest::ensure_eq(center.toasts(2_500).len(), 1, "descartada")?;
+        crate::selftest::ensure_eq(center.history().len(), 2, "sigue en historial")
+    }
+}
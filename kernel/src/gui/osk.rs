@@ -0,0 +1,946 @@
+//! On-screen keyboard for machines without a physical one (2-in-1 tablets).
+//!
+//! The keyboard is an overlay the compositor draws above the taskbar. With
+//! `input.osk = auto` (the default) it comes up while a text window has the
+//! focus and no hardware key has been pressed for `HARDWARE_IDLE_MS`; `on`
+//! shows it whenever a text window is focused and `off` never does. Taps are
+//! turned into ordinary `KeyboardEvent`s pushed through
+//! `event_queue::push_synthetic`, so windows cannot tell them from real keys.
+//!
+//! Holding a letter for `LONG_PRESS_MS` opens a strip with its accented
+//! forms; sliding onto one and releasing types it. A suggestion bar offers
+//! completions and single-edit corrections for the word being typed, from a
+//! small built-in list per layout. Only words typed on the keyboard itself
+//! are tracked: a hardware key or a focus change forgets the current word.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::KeyboardEvent;
+use crate::config::ConfigValue;
+use crate::framebuffer;
+use crate::spinlock::SpinLock;
+
+pub const MODE_KEY: &str = "input.osk";
+/// Hold time before the accent strip opens.
+pub const LONG_PRESS_MS: u64 = 500;
+/// How long after the last hardware key `auto` mode stays out of the way.
+pub const HARDWARE_IDLE_MS: u64 = 60_000;
+pub const MAX_SUGGESTIONS: usize = 3;
+
+const MAX_WIDTH: usize = 720;
+const BAR_H: usize = 18;
+const PAD: usize = 4;
+const GAP: usize = 2;
+const KEY_ROWS: usize = 5;
+
+const COLOR_BG: u32 = 0x111827;
+const COLOR_BORDER: u32 = 0x38BDF8;
+const COLOR_KEY: u32 = 0x1F2937;
+const COLOR_KEY_SPECIAL: u32 = 0x273449;
+const COLOR_KEY_ACTIVE: u32 = 0x2563EB;
+const COLOR_BAR: u32 = 0x0B1220;
+const COLOR_TEXT: u32 = 0xE5E7EB;
+const COLOR_TEXT_DIM: u32 = 0x9CA3AF;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Auto,
+    On,
+    Off,
+}
+
+impl Mode {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "auto" => Some(Mode::Auto),
+            "on" => Some(Mode::On),
+            "off" => Some(Mode::Off),
+            _ => None,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Mode::Auto => "auto",
+            Mode::On => "on",
+            Mode::Off => "off",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    Es,
+    En,
+}
+
+impl Layout {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "es" => Some(Layout::Es),
+            "en" => Some(Layout::En),
+            _ => None,
+        }
+    }
+
+    /// The layout matching the UI language.
+    pub fn from_locale() -> Self {
+        if crate::i18n::current_code() == "en" {
+            Layout::En
+        } else {
+            Layout::Es
+        }
+    }
+
+    pub const fn code(self) -> &'static str {
+        match self {
+            Layout::Es => "es",
+            Layout::En => "en",
+        }
+    }
+
+    const fn other(self) -> Self {
+        match self {
+            Layout::Es => Layout::En,
+            Layout::En => Layout::Es,
+        }
+    }
+
+    fn rows(self) -> [&'static str; 4] {
+        match self {
+            Layout::Es => ["1234567890", "qwertyuiop", "asdfghjklñ", "zxcvbnm,."],
+            Layout::En => ["1234567890", "qwertyuiop", "asdfghjkl'", "zxcvbnm,."],
+        }
+    }
+
+    fn words(self) -> &'static [&'static str] {
+        match self {
+            Layout::Es => WORDS_ES,
+            Layout::En => WORDS_EN,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Shift,
+    Backspace,
+    Layout,
+    Space,
+    Enter,
+    Hide,
+    Suggestion(usize),
+}
+
+/// Special keys on the bottom row, with their width in half keys.
+const BOTTOM_ROW: [(Key, usize); 6] = [
+    (Key::Shift, 3),
+    (Key::Layout, 2),
+    (Key::Space, 7),
+    (Key::Backspace, 3),
+    (Key::Enter, 3),
+    (Key::Hide, 2),
+];
+
+/// Alternatives offered on long press.
+pub fn accents(layout: Layout, ch: char) -> &'static [char] {
+    match (layout, ch) {
+        (Layout::Es, 'a') => &['á', 'à'],
+        (Layout::Es, 'e') => &['é'],
+        (Layout::Es, 'i') => &['í'],
+        (Layout::Es, 'o') => &['ó'],
+        (Layout::Es, 'u') => &['ú', 'ü'],
+        (Layout::Es, 'n') => &['ñ'],
+        (Layout::Es, '.') => &['?', '!', '¿', '¡'],
+        (Layout::En, 'a') => &['à', 'á', 'â', 'ä'],
+        (Layout::En, 'e') => &['è', 'é', 'ê', 'ë'],
+        (Layout::En, 'i') => &['ì', 'í', 'î', 'ï'],
+        (Layout::En, 'o') => &['ò', 'ó', 'ô', 'ö'],
+        (Layout::En, 'u') => &['ù', 'ú', 'û', 'ü'],
+        (Layout::En, 'n') => &['ñ'],
+        (Layout::En, '.') => &['?', '!', ':', ';'],
+        (_, 'c') => &['ç'],
+        (_, ',') => &['-', ';', ':'],
+        _ => &[],
+    }
+}
+
+const WORDS_ES: &[&str] = &[
+    "que",
+    "de",
+    "no",
+    "la",
+    "el",
+    "en",
+    "los",
+    "las",
+    "un",
+    "una",
+    "por",
+    "para",
+    "con",
+    "como",
+    "pero",
+    "más",
+    "ya",
+    "este",
+    "esta",
+    "está",
+    "porque",
+    "entre",
+    "cuando",
+    "muy",
+    "sin",
+    "sobre",
+    "también",
+    "hasta",
+    "hay",
+    "donde",
+    "desde",
+    "todo",
+    "todos",
+    "nada",
+    "algo",
+    "mucho",
+    "poco",
+    "bien",
+    "hola",
+    "gracias",
+    "después",
+    "antes",
+    "ahora",
+    "siempre",
+    "nunca",
+    "año",
+    "día",
+    "mañana",
+    "tarde",
+    "noche",
+    "casa",
+    "trabajo",
+    "tiempo",
+    "archivo",
+    "archivos",
+    "carpeta",
+    "sistema",
+    "ventana",
+    "terminal",
+    "programa",
+    "red",
+    "correo",
+    "mensaje",
+    "contraseña",
+    "usuario",
+    "configuración",
+    "información",
+    "aplicación",
+    "pantalla",
+    "teclado",
+    "español",
+    "inglés",
+    "buscar",
+    "abrir",
+    "cerrar",
+    "guardar",
+    "borrar",
+    "nuevo",
+    "nueva",
+];
+
+const WORDS_EN: &[&str] = &[
+    "the", "and", "that", "have", "for", "not", "with", "you", "this", "but", "from", "they", "will", "would", "there",
+    "their", "what", "about", "which", "when", "make", "can", "like", "time", "just", "know", "take", "people", "into",
+    "year", "your", "good", "some", "could", "them", "see", "other", "than", "then", "now", "look", "only", "come",
+    "over", "think", "also", "back", "after", "use", "how", "our", "work", "first", "well", "way", "even", "new",
+    "want", "because", "any", "these", "give", "day", "most", "hello", "thanks", "please", "file", "files", "folder",
+    "system", "window", "terminal", "program", "network", "mail", "message", "password", "user", "settings", "screen",
+    "keyboard", "search", "open", "close", "save", "delete",
+];
+
+fn lower(word: &str) -> String {
+    word.chars().flat_map(char::to_lowercase).collect()
+}
+
+/// Whether `a` turns into `b` with one insertion, deletion, substitution or
+/// swap of neighbouring letters.
+fn one_edit_apart(a: &[char], b: &[char]) -> bool {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if long.len() - short.len() > 1 {
+        return false;
+    }
+    let mut i = 0;
+    while i < short.len() && short[i] == long[i] {
+        i += 1;
+    }
+    if short.len() == long.len() {
+        let swapped = i + 1 < short.len()
+            && short[i] == long[i + 1]
+            && short[i + 1] == long[i]
+            && short[i + 2..] == long[i + 2..];
+        (i < short.len() && short[i + 1..] == long[i + 1..]) || swapped
+    } else {
+        short[i..] == long[i + 1..]
+    }
+}
+
+/// Up to `MAX_SUGGESTIONS` words for `word`: first corrections when the word
+/// (three letters or more) is not known, then completions.
+pub fn suggestions(layout: Layout, word: &str) -> Vec<&'static str> {
+    let mut out: Vec<&'static str> = Vec::new();
+    if word.is_empty() {
+        return out;
+    }
+    let typed = lower(word);
+    let words = layout.words();
+    let typed_chars: Vec<char> = typed.chars().collect();
+    if typed_chars.len() >= 3 && !words.iter().any(|w| *w == typed.as_str()) {
+        for candidate in words.iter() {
+            let chars: Vec<char> = candidate.chars().collect();
+            if one_edit_apart(&typed_chars, &chars) {
+                out.push(candidate);
+                if out.len() == MAX_SUGGESTIONS {
+                    return out;
+                }
+            }
+        }
+    }
+    for candidate in words.iter() {
+        if candidate.len() > typed.len() && candidate.starts_with(typed.as_str()) && !out.contains(candidate) {
+            out.push(candidate);
+            if out.len() == MAX_SUGGESTIONS {
+                break;
+            }
+        }
+    }
+    out
+}
+
+/// Screen placement of the keyboard, recomputed on every draw.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Geometry {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+    pub key_w: usize,
+    pub key_h: usize,
+}
+
+impl Geometry {
+    /// Full width up to `MAX_WIDTH`, centred, sitting on `bottom`.
+    pub fn new(screen_w: usize, bottom: usize) -> Self {
+        let key_w = (screen_w.min(MAX_WIDTH).saturating_sub(2 * PAD) / 10).max(12);
+        let key_h = (bottom / 14).clamp(22, 44);
+        let w = key_w * 10 + 2 * PAD;
+        let h = BAR_H + KEY_ROWS * key_h + 2 * PAD;
+        Self {
+            x: screen_w.saturating_sub(w) / 2,
+            y: bottom.saturating_sub(h),
+            w,
+            h,
+            key_w,
+            key_h,
+        }
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x as i32 && y >= self.y as i32 && x < (self.x + self.w) as i32 && y < (self.y + self.h) as i32
+    }
+
+    /// Every key with its (x, y, w, h), suggestion cells first.
+    fn for_each_key(&self, layout: Layout, mut f: impl FnMut(Key, usize, usize, usize, usize)) {
+        let cell_w = (self.w - 2 * PAD) / MAX_SUGGESTIONS;
+        for i in 0..MAX_SUGGESTIONS {
+            f(
+                Key::Suggestion(i),
+                self.x + PAD + i * cell_w,
+                self.y + PAD,
+                cell_w,
+                BAR_H,
+            );
+        }
+        let top = self.y + PAD + BAR_H;
+        for (row, chars) in layout.rows().iter().enumerate() {
+            let count = chars.chars().count();
+            let left = self.x + PAD + (10 - count) * self.key_w / 2;
+            for (col, ch) in chars.chars().enumerate() {
+                f(
+                    Key::Char(ch),
+                    left + col * self.key_w,
+                    top + row * self.key_h,
+                    self.key_w,
+                    self.key_h,
+                );
+            }
+        }
+        let mut x = self.x + PAD;
+        let y = top + 4 * self.key_h;
+        for (key, halves) in BOTTOM_ROW.iter() {
+            let w = halves * self.key_w / 2;
+            f(*key, x, y, w, self.key_h);
+            x += w;
+        }
+    }
+
+    pub fn hit(&self, layout: Layout, px: i32, py: i32) -> Option<(Key, usize, usize)> {
+        let mut found = None;
+        self.for_each_key(layout, |key, x, y, w, h| {
+            if px >= x as i32 && py >= y as i32 && px < (x + w) as i32 && py < (y + h) as i32 {
+                found = Some((key, x, y));
+            }
+        });
+        found
+    }
+}
+
+/// Accent strip opened by a long press, one key wide per choice.
+#[derive(Clone, Copy, Debug)]
+struct Popup {
+    choices: &'static [char],
+    x: usize,
+    y: usize,
+    hover: Option<usize>,
+}
+
+impl Popup {
+    fn hit(&self, geo: &Geometry, px: i32, py: i32) -> Option<usize> {
+        if py < self.y as i32 || py >= (self.y + geo.key_h) as i32 || px < self.x as i32 {
+            return None;
+        }
+        let idx = (px as usize - self.x) / geo.key_w;
+        (idx < self.choices.len()).then_some(idx)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Press {
+    key: Key,
+    at_ms: u64,
+    key_x: usize,
+    key_y: usize,
+}
+
+struct State {
+    mode: Mode,
+    layout: Option<Layout>,
+    text_focus: bool,
+    /// Hidden with the hide key until the focus moves.
+    dismissed: bool,
+    /// Shown with `osk show` regardless of focus.
+    forced: bool,
+    shown: bool,
+    last_hardware_ms: Option<u64>,
+    shift: bool,
+    press: Option<Press>,
+    popup: Option<Popup>,
+    word: String,
+    area: Option<Geometry>,
+    dirty: bool,
+}
+
+static OSK: SpinLock<State> = SpinLock::new(State {
+    mode: Mode::Auto,
+    layout: None,
+    text_focus: false,
+    dismissed: false,
+    forced: false,
+    shown: false,
+    last_hardware_ms: None,
+    shift: false,
+    press: None,
+    popup: None,
+    word: String::new(),
+    area: None,
+    dirty: false,
+});
+
+impl State {
+    fn layout(&self) -> Layout {
+        self.layout.unwrap_or_else(Layout::from_locale)
+    }
+
+    fn wants_visible(&self, now_ms: u64) -> bool {
+        if self.mode == Mode::Off {
+            return false;
+        }
+        if self.forced {
+            return true;
+        }
+        if !self.text_focus || self.dismissed {
+            return false;
+        }
+        match self.mode {
+            Mode::Auto => self
+                .last_hardware_ms
+                .map(|at| now_ms.saturating_sub(at) >= HARDWARE_IDLE_MS)
+                .unwrap_or(true),
+            _ => true,
+        }
+    }
+
+    fn reset_input(&mut self) {
+        self.shift = false;
+        self.press = None;
+        self.popup = None;
+        self.word.clear();
+    }
+
+    /// Characters to send for a tapped key.
+    fn activate(&mut self, key: Key) -> Vec<char> {
+        let mut out = Vec::new();
+        match key {
+            Key::Char(ch) => {
+                let ch = if self.shift {
+                    ch.to_uppercase().next().unwrap_or(ch)
+                } else {
+                    ch
+                };
+                self.shift = false;
+                if ch.is_alphabetic() {
+                    self.word.push(ch);
+                } else {
+                    self.word.clear();
+                }
+                out.push(ch);
+            }
+            Key::Shift => self.shift = !self.shift,
+            Key::Backspace => {
+                self.word.pop();
+                out.push('\x08');
+            }
+            Key::Layout => {
+                self.layout = Some(self.layout().other());
+                self.word.clear();
+            }
+            Key::Space => {
+                self.word.clear();
+                out.push(' ');
+            }
+            Key::Enter => {
+                self.word.clear();
+                out.push('\n');
+            }
+            Key::Hide => {
+                self.dismissed = true;
+                self.forced = false;
+                self.reset_input();
+            }
+            Key::Suggestion(idx) => {
+                let list = suggestions(self.layout(), self.word.as_str());
+                if let Some(word) = list.get(idx) {
+                    let capital = self.word.chars().next().map(char::is_uppercase).unwrap_or(false);
+                    out.extend(core::iter::repeat('\x08').take(self.word.chars().count()));
+                    for (i, ch) in word.chars().enumerate() {
+                        out.push(if capital && i == 0 {
+                            ch.to_uppercase().next().unwrap_or(ch)
+                        } else {
+                            ch
+                        });
+                    }
+                    out.push(' ');
+                    self.word.clear();
+                }
+            }
+        }
+        out
+    }
+}
+
+fn send(chars: &[char]) {
+    for ch in chars.iter() {
+        super::event_queue::push_synthetic(KeyboardEvent {
+            key: Some(*ch),
+            special: None,
+            down: true,
+        });
+    }
+}
+
+/// A key arrived from a physical keyboard.
+pub fn note_hardware_key() {
+    let now = crate::timer::now_ms();
+    let mut st = OSK.lock();
+    st.last_hardware_ms = Some(now);
+    st.word.clear();
+}
+
+/// Per-frame update from the compositor: whether a text window has the
+/// focus, plus the long-press timer. Returns true when a repaint is needed.
+pub fn service(text_focus: bool, now_ms: u64) -> bool {
+    let mut st = OSK.lock();
+    if st.text_focus != text_focus {
+        st.text_focus = text_focus;
+        st.dismissed = false;
+        st.reset_input();
+    }
+    let visible = st.wants_visible(now_ms);
+    if visible != st.shown {
+        st.shown = visible;
+        if !visible {
+            st.reset_input();
+            st.area = None;
+        }
+        st.dirty = true;
+    }
+    if let (Some(press), None, Some(geo)) = (st.press, st.popup, st.area) {
+        if let Key::Char(ch) = press.key {
+            let choices = accents(st.layout(), ch);
+            if !choices.is_empty() && now_ms.saturating_sub(press.at_ms) >= LONG_PRESS_MS {
+                let width = choices.len() * geo.key_w;
+                let x = press.key_x.min((geo.x + geo.w).saturating_sub(width)).max(geo.x);
+                st.popup = Some(Popup {
+                    choices,
+                    x,
+                    y: press.key_y.saturating_sub(geo.key_h),
+                    hover: None,
+                });
+                st.dirty = true;
+            }
+        }
+    }
+    core::mem::take(&mut st.dirty)
+}
+
+pub fn is_visible() -> bool {
+    OSK.lock().shown
+}
+
+/// Pointer report from the compositor, before any window sees it. Returns
+/// true when the keyboard took it: presses inside the keyboard and
+/// everything until the matching release.
+pub fn pointer(x: i32, y: i32, down: bool, now_ms: u64) -> bool {
+    let mut st = OSK.lock();
+    let Some(geo) = st.area.filter(|_| st.shown) else {
+        return false;
+    };
+    let Some(press) = st.press else {
+        if !down || !geo.contains(x, y) {
+            return false;
+        }
+        st.press = geo.hit(st.layout(), x, y).map(|(key, key_x, key_y)| Press {
+            key,
+            at_ms: now_ms,
+            key_x,
+            key_y,
+        });
+        st.dirty = true;
+        return true;
+    };
+    if down {
+        if let Some(mut popup) = st.popup {
+            let hover = popup.hit(&geo, x, y);
+            if popup.hover != hover {
+                popup.hover = hover;
+                st.popup = Some(popup);
+                st.dirty = true;
+            }
+        }
+        return true;
+    }
+    st.press = None;
+    st.dirty = true;
+    let key = match st.popup.take() {
+        Some(popup) => popup.hit(&geo, x, y).map(|idx| Key::Char(popup.choices[idx])),
+        None => geo
+            .hit(st.layout(), x, y)
+            .map(|(key, _, _)| key)
+            .filter(|key| *key == press.key),
+    };
+    let Some(key) = key else {
+        return true;
+    };
+    let chars = st.activate(key);
+    drop(st);
+    send(&chars);
+    true
+}
+
+/// Inverted question and exclamation marks, which the 5x7 font lacks.
+const INVERTED_QUESTION: [u8; 7] = [0x04, 0, 0x04, 0x08, 0x10, 0x11, 0x0E];
+const INVERTED_EXCLAMATION: [u8; 7] = [0x04, 0, 0x04, 0x04, 0x04, 0x04, 0x04];
+
+fn draw_bitmap(x: usize, y: usize, rows: &[u8; 7], color: u32) {
+    for (row, bits) in rows.iter().enumerate() {
+        for col in 0..5 {
+            if bits & (1 << (4 - col)) != 0 {
+                framebuffer::pixel(x + col, y + row, color);
+            }
+        }
+    }
+}
+
+/// Draw one key label. Accented letters are the base glyph with the mark
+/// drawn on top, since the font only covers ASCII.
+fn draw_char(x: usize, y: usize, ch: char, color: u32) {
+    let (base, mark) = match ch {
+        'á' | 'é' | 'í' | 'ó' | 'ú' => (ch, 1),
+        'à' | 'è' | 'ì' | 'ò' | 'ù' => (ch, 2),
+        'â' | 'ê' | 'î' | 'ô' | 'û' => (ch, 3),
+        'ä' | 'ë' | 'ï' | 'ö' | 'ü' => (ch, 4),
+        'ñ' => ('n', 5),
+        'ç' => ('c', 6),
+        '¿' => return draw_bitmap(x, y, &INVERTED_QUESTION, color),
+        '¡' => return draw_bitmap(x, y, &INVERTED_EXCLAMATION, color),
+        _ => (ch, 0),
+    };
+    let base = match base {
+        'á' | 'à' | 'â' | 'ä' => 'a',
+        'é' | 'è' | 'ê' | 'ë' => 'e',
+        'í' | 'ì' | 'î' | 'ï' => 'i',
+        'ó' | 'ò' | 'ô' | 'ö' => 'o',
+        'ú' | 'ù' | 'û' | 'ü' => 'u',
+        other => other,
+    };
+    framebuffer::draw_char_5x7(x, y, base, color);
+    let top = y.saturating_sub(3);
+    match mark {
+        1 => {
+            framebuffer::pixel(x + 3, top, color);
+            framebuffer::pixel(x + 2, top + 1, color);
+        }
+        2 => {
+            framebuffer::pixel(x + 1, top, color);
+            framebuffer::pixel(x + 2, top + 1, color);
+        }
+        3 => {
+            framebuffer::pixel(x + 2, top, color);
+            framebuffer::pixel(x + 1, top + 1, color);
+            framebuffer::pixel(x + 3, top + 1, color);
+        }
+        4 => {
+            framebuffer::pixel(x + 1, top + 1, color);
+            framebuffer::pixel(x + 3, top + 1, color);
+        }
+        5 => {
+            framebuffer::pixel(x, top + 1, color);
+            framebuffer::pixel(x + 1, top, color);
+            framebuffer::pixel(x + 2, top + 1, color);
+            framebuffer::pixel(x + 3, top, color);
+        }
+        6 => {
+            framebuffer::pixel(x + 2, y + 7, color);
+            framebuffer::pixel(x + 1, y + 8, color);
+        }
+        _ => {}
+    }
+}
+
+fn draw_text(x: usize, y: usize, text: &str, color: u32) {
+    for (i, ch) in text.chars().enumerate() {
+        draw_char(x + i * 6, y, ch, color);
+    }
+}
+
+fn key_label(key: Key, layout: Layout) -> &'static str {
+    match key {
+        Key::Shift => "SHIFT",
+        Key::Backspace => "BORRAR",
+        Key::Layout => match layout {
+            Layout::Es => "ES",
+            Layout::En => "EN",
+        },
+        Key::Space => "ESPACIO",
+        Key::Enter => "ENTER",
+        Key::Hide => "OCULTAR",
+        _ => "",
+    }
+}
+
+/// Draw the keyboard with its bottom edge at `bottom` (the taskbar top).
+pub fn draw(screen_w: usize, bottom: usize) {
+    let geo = Geometry::new(screen_w, bottom);
+    let (layout, shift, pressed, popup, word) = {
+        let mut st = OSK.lock();
+        if !st.shown {
+            st.area = None;
+            return;
+        }
+        st.area = Some(geo);
+        (
+            st.layout(),
+            st.shift,
+            st.press.map(|p| p.key),
+            st.popup,
+            st.word.clone(),
+        )
+    };
+    let hints = suggestions(layout, word.as_str());
+
+    framebuffer::rect(geo.x, geo.y, geo.w, geo.h, COLOR_BG);
+    framebuffer::rect(geo.x, geo.y, geo.w, 1, COLOR_BORDER);
+    geo.for_each_key(layout, |key, x, y, w, h| {
+        if let Key::Suggestion(idx) = key {
+            framebuffer::rect(x + GAP / 2, y, w - GAP, h - GAP, COLOR_BAR);
+            if let Some(hint) = hints.get(idx) {
+                let chars = hint.chars().count();
+                let tx = x + w.saturating_sub(chars * 6) / 2;
+                draw_text(tx, y + (h - 7) / 2, hint, COLOR_TEXT);
+            }
+            return;
+        }
+        let active = pressed == Some(key) || (key == Key::Shift && shift);
+        let fill = match (active, key) {
+            (true, _) => COLOR_KEY_ACTIVE,
+            (false, Key::Char(_)) => COLOR_KEY,
+            _ => COLOR_KEY_SPECIAL,
+        };
+        framebuffer::rect(x + GAP / 2, y + GAP / 2, w - GAP, h - GAP, fill);
+        if let Key::Char(ch) = key {
+            draw_char(x + (w - 5) / 2, y + (h - 7) / 2, ch, COLOR_TEXT);
+            // Dot in the corner: a long press has accents.
+            if !accents(layout, ch).is_empty() && w >= 20 {
+                framebuffer::rect(x + w - GAP - 4, y + GAP + 2, 2, 2, COLOR_TEXT_DIM);
+            }
+            return;
+        }
+        let text = key_label(key, layout);
+        let label_w = text.len() * 6;
+        if label_w + 4 <= w {
+            framebuffer::draw_text_5x7(x + (w - label_w) / 2, y + (h - 7) / 2, text, COLOR_TEXT_DIM);
+        }
+    });
+
+    if let Some(popup) = popup {
+        let width = popup.choices.len() * geo.key_w;
+        framebuffer::rect(popup.x, popup.y, width, geo.key_h, COLOR_BORDER);
+        for (idx, ch) in popup.choices.iter().enumerate() {
+            let x = popup.x + idx * geo.key_w;
+            let fill = if popup.hover == Some(idx) {
+                COLOR_KEY_ACTIVE
+            } else {
+                COLOR_BG
+            };
+            framebuffer::rect(x + 1, popup.y + 1, geo.key_w - 2, geo.key_h - 2, fill);
+            draw_char(x + (geo.key_w - 5) / 2, popup.y + (geo.key_h - 7) / 2, *ch, COLOR_TEXT);
+        }
+    }
+}
+
+fn on_mode_changed(_key: &str, value: Option<&ConfigValue>) {
+    let mode = match value {
+        Some(ConfigValue::Str(text)) => Mode::parse(text.as_str()).unwrap_or(Mode::Auto),
+        Some(ConfigValue::Bool(false)) => Mode::Off,
+        Some(ConfigValue::Bool(true)) => Mode::On,
+        _ => Mode::Auto,
+    };
+    let mut st = OSK.lock();
+    st.mode = mode;
+    st.dirty = true;
+}
+
+/// Follow `input.osk` in the config registry.
+pub fn init() {
+    crate::config::watch(MODE_KEY, on_mode_changed);
+}
+
+pub fn load_config() {
+    on_mode_changed(MODE_KEY, crate::config::get(MODE_KEY).as_ref());
+}
+
+/// Shared implementation of `osk`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let arg = args.trim().to_ascii_lowercase();
+    let mut parts = arg.split_whitespace();
+    let mut out = Vec::new();
+    match parts.next() {
+        None | Some("status") => {
+            let st = OSK.lock();
+            out.push(alloc::format!(
+                "teclado en pantalla: modo {}, distribucion {}, visible: {}",
+                st.mode.name(),
+                st.layout().code(),
+                if st.shown { "si" } else { "no" }
+            ));
+        }
+        Some(word) if Mode::parse(word).is_some() => {
+            match crate::config::set(MODE_KEY, ConfigValue::Str(String::from(word))) {
+                Ok(()) => out.push(alloc::format!("osk: modo {}", word)),
+                Err(err) => out.push(alloc::format!("osk: {}", err)),
+            }
+        }
+        Some("show") => {
+            let mut st = OSK.lock();
+            if st.mode == Mode::Off {
+                out.push(String::from("osk: desactivado (usa osk auto u osk on)"));
+            } else {
+                st.forced = true;
+                st.dirty = true;
+                out.push(String::from("osk: teclado visible"));
+            }
+        }
+        Some("hide") => {
+            let mut st = OSK.lock();
+            st.forced = false;
+            st.dismissed = true;
+            st.dirty = true;
+            out.push(String::from("osk: teclado oculto"));
+        }
+        Some("layout") => match parts.next().and_then(Layout::parse) {
+            Some(layout) => {
+                let mut st = OSK.lock();
+                st.layout = Some(layout);
+                st.word.clear();
+                st.dirty = true;
+                out.push(alloc::format!("osk: distribucion {}", layout.code()));
+            }
+            None => out.push(String::from("uso: osk layout es|en")),
+        },
+        Some(_) => out.push(String::from("uso: osk [on|off|auto|show|hide|layout es|en]")),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "osk";
+
+    fn layouts_and_hit_test() {
+        let geo = Geometry::new(800, 600);
+        crate::selftest::ensure(geo.y + geo.h == 600, "apoyado sobre la barra")?;
+        let key_y = geo.y + PAD + BAR_H + 2 * geo.key_h + geo.key_h / 2;
+        let last = (geo.x + PAD + 9 * geo.key_w + geo.key_w / 2) as i32;
+        let es = geo.hit(Layout::Es, last, key_y as i32).map(|(key, _, _)| key);
+        let en = geo.hit(Layout::En, last, key_y as i32).map(|(key, _, _)| key);
+        crate::selftest::ensure_eq(es, Some(Key::Char('ñ')), "tecla ene")?;
+        crate::selftest::ensure_eq(en, Some(Key::Char('\'')), "apostrofo en")?;
+        let bottom = (geo.y + PAD + BAR_H + 4 * geo.key_h + 1) as i32;
+        let space = geo.hit(Layout::Es, (geo.x + geo.w / 2) as i32, bottom).map(|(key, _, _)| key);
+        crate::selftest::ensure_eq(space, Some(Key::Space), "espacio")?;
+        crate::selftest::ensure_eq(accents(Layout::Es, 'u'), &['ú', 'ü'][..], "acentos u")?;
+        crate::selftest::ensure(accents(Layout::Es, 'x').is_empty(), "sin acentos")
+    }
+
+    fn suggestions_complete_and_correct() {
+        crate::selftest::ensure_eq(suggestions(Layout::Es, "tamb"), alloc::vec!["también"], "completar")?;
+        crate::selftest::ensure_eq(suggestions(Layout::En, "teh").first().copied(), Some("the"), "corregir")?;
+        crate::selftest::ensure_eq(suggestions(Layout::En, "thr").first().copied(), Some("the"), "sustituir")?;
+        crate::selftest::ensure(suggestions(Layout::En, "").is_empty(), "vacio")?;
+        crate::selftest::ensure(one_edit_apart(&['c', 'a', 's'], &['c', 'a', 's', 'a']), "insercion")?;
+        crate::selftest::ensure(!one_edit_apart(&['a', 'b'], &['b', 'a', 'c', 'd']), "lejos")
+    }
+
+    fn suggestion_replaces_current_word() {
+        let mut st = State {
+            mode: Mode::On,
+            layout: Some(Layout::Es),
+            text_focus: true,
+            dismissed: false,
+            forced: false,
+            shown: true,
+            last_hardware_ms: None,
+            shift: true,
+            press: None,
+            popup: None,
+            word: String::new(),
+            area: None,
+            dirty: false,
+        };
+        crate::selftest::ensure_eq(st.activate(Key::Char('c')), alloc::vec!['C'], "shift de un uso")?;
+        st.activate(Key::Char('a'));
+        st.activate(Key::Char('r'));
+        let sent: String = st.activate(Key::Suggestion(0)).into_iter().collect();
+        crate::selftest::ensure_eq(sent.as_str(), "\x08\x08\x08Carpeta ", "sugerencia")?;
+        crate::selftest::ensure(st.word.is_empty(), "palabra reiniciada")
+    }
+}
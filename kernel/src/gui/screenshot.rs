@@ -0,0 +1,75 @@
+//! Desktop screenshots, encoded as 24-bit BMP so they need no compressor.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::framebuffer;
+
+static NEXT_SHOT: AtomicU32 = AtomicU32::new(1);
+
+/// Bottom-up 24-bit BMP of `width * height` 0xRRGGBB pixels given top row first.
+pub fn encode_bmp(width: usize, height: usize, pixels: &[u32]) -> Vec<u8> {
+    // Rows are padded to a multiple of 4 bytes.
+    let row_bytes = (width * 3 + 3) & !3;
+    let data_len = row_bytes * height;
+    let file_len = 54 + data_len;
+    let mut out = Vec::with_capacity(file_len);
+    out.extend_from_slice(b"BM");
+    out.extend_from_slice(&(file_len as u32).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&54u32.to_le_bytes());
+    out.extend_from_slice(&40u32.to_le_bytes());
+    out.extend_from_slice(&(width as i32).to_le_bytes());
+    out.extend_from_slice(&(height as i32).to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&24u16.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&(data_len as u32).to_le_bytes());
+    // 2835 pixels per metre, about 72 dpi.
+    out.extend_from_slice(&2835i32.to_le_bytes());
+    out.extend_from_slice(&2835i32.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    for y in (0..height).rev() {
+        let row = &pixels[y * width..(y + 1) * width];
+        for &px in row {
+            out.push(px as u8);
+            out.push((px >> 8) as u8);
+            out.push((px >> 16) as u8);
+        }
+        out.resize(out.len() + row_bytes - width * 3, 0);
+    }
+    out
+}
+
+/// What is on screen now, as a BMP file.
+pub fn capture_bmp() -> Option<Vec<u8>> {
+    let (width, height) = framebuffer::dimensions();
+    let pixels = framebuffer::capture();
+    if width == 0 || height == 0 || pixels.len() < width * height {
+        return None;
+    }
+    Some(encode_bmp(width, height, pixels.as_slice()))
+}
+
+/// Suggested 8.3 name for the next capture: CAPT0001.BMP, CAPT0002.BMP...
+pub fn next_name() -> String {
+    let n = NEXT_SHOT.fetch_add(1, Ordering::Relaxed) % 10_000;
+    alloc::format!("CAPT{:04}.BMP", n)
+}
+
+crate::selftest::kernel_tests! {
+    "screenshot";
+
+    fn bmp_header_and_row_padding() {
+        let pixels = [0x112233, 0x445566, 0x778899, 0xAABBCC];
+        let bmp = encode_bmp(1, 4, &pixels);
+        crate::selftest::ensure_eq(&bmp[0..2], b"BM".as_slice(), "firma")?;
+        crate::selftest::ensure_eq(bmp.len(), 54 + 4 * 4, "filas de 4 bytes")?;
+        crate::selftest::ensure_eq(u32::from_le_bytes([bmp[2], bmp[3], bmp[4], bmp[5]]) as usize, bmp.len(), "tamano")?;
+        // Bottom row first, stored as B, G, R.
+        crate::selftest::ensure_eq(&bmp[54..57], [0xCC, 0xBB, 0xAA].as_slice(), "ultima fila primero")?;
+        crate::selftest::ensure_eq(&bmp[66..69], [0x33, 0x22, 0x11].as_slice(), "primera fila al final")
+    }
+}
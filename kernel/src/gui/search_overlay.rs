@@ -0,0 +1,265 @@
+//! Quick search overlay (Super+Space): a query field over the desktop with
+//! the best apps, settings and indexed files listed below it.
+//!
+//! The overlay only edits the query and picks a row; the compositor fills in
+//! the results (from `crate::search_index` and its app list) after every
+//! change and launches the chosen command.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::window::SearchResultEntry;
+use super::{Point, Rect, SpecialKey};
+use crate::framebuffer;
+
+const PANEL_W: u32 = 600;
+const FIELD_H: u32 = 30;
+const ROW_H: u32 = 30;
+const PAD: i32 = 10;
+const STATUS_H: u32 = 20;
+pub const QUERY_MAX: usize = 80;
+pub const RESULTS_MAX: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QuickSearchOutcome {
+    /// Input was consumed; nothing else to do.
+    None,
+    /// The query changed: the results must be computed again.
+    QueryChanged,
+    /// Launch the result at this index.
+    Open(usize),
+    Close,
+}
+
+#[derive(Default)]
+pub struct QuickSearch {
+    pub query: String,
+    results: Vec<SearchResultEntry>,
+    selected: usize,
+    /// Footer line, e.g. how far the index is.
+    status: String,
+}
+
+fn trim_to(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => &text[..idx],
+        None => text,
+    }
+}
+
+fn tail_to(text: &str, max_chars: usize) -> &str {
+    let count = text.chars().count();
+    match text.char_indices().nth(count.saturating_sub(max_chars)) {
+        Some((idx, _)) => &text[idx..],
+        None => text,
+    }
+}
+
+fn fill(rect: Rect, color: u32) {
+    framebuffer::rect(
+        rect.x.max(0) as usize,
+        rect.y.max(0) as usize,
+        rect.width as usize,
+        rect.height as usize,
+        color,
+    );
+}
+
+fn text(x: i32, y: i32, value: &str, color: u32) {
+    framebuffer::draw_text_5x7(x.max(0) as usize, y.max(0) as usize, value, color);
+}
+
+impl QuickSearch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_results(&mut self, mut results: Vec<SearchResultEntry>, status: &str) {
+        results.truncate(RESULTS_MAX);
+        self.selected = self.selected.min(results.len().saturating_sub(1));
+        self.results = results;
+        self.status = String::from(status);
+    }
+
+    pub fn result(&self, index: usize) -> Option<&SearchResultEntry> {
+        self.results.get(index)
+    }
+
+    /// Centered horizontally, a fifth of the way down `bounds`; grows with
+    /// the number of results.
+    pub fn rect(&self, bounds: Rect) -> Rect {
+        let w = PANEL_W.min(bounds.width.saturating_sub(20));
+        let h = (FIELD_H + 2 * PAD as u32 + self.results.len() as u32 * ROW_H + STATUS_H).min(bounds.height);
+        Rect::new(
+            bounds.x + (bounds.width - w) as i32 / 2,
+            bounds.y + (bounds.height - h) as i32 / 5,
+            w,
+            h,
+        )
+    }
+
+    fn field_rect(rect: Rect) -> Rect {
+        Rect::new(
+            rect.x + PAD,
+            rect.y + PAD,
+            rect.width.saturating_sub(2 * PAD as u32),
+            FIELD_H,
+        )
+    }
+
+    fn row_rect(rect: Rect, row: usize) -> Rect {
+        Rect::new(
+            rect.x + PAD,
+            rect.y + PAD + FIELD_H as i32 + 4 + (row as u32 * ROW_H) as i32,
+            rect.width.saturating_sub(2 * PAD as u32),
+            ROW_H,
+        )
+    }
+
+    pub fn key(&mut self, key: Option<char>, special: Option<SpecialKey>) -> QuickSearchOutcome {
+        match (key, special) {
+            (Some('\x1b'), _) => QuickSearchOutcome::Close,
+            (Some('\n'), _) | (Some('\r'), _) if !self.results.is_empty() => QuickSearchOutcome::Open(self.selected),
+            (_, Some(SpecialKey::Up)) => {
+                self.selected = self.selected.saturating_sub(1);
+                QuickSearchOutcome::None
+            }
+            (_, Some(SpecialKey::Down)) | (Some('\t'), _) => {
+                if self.selected + 1 < self.results.len() {
+                    self.selected += 1;
+                }
+                QuickSearchOutcome::None
+            }
+            (Some('\x08'), _) | (Some('\x7f'), _) => {
+                if self.query.pop().is_some() {
+                    self.selected = 0;
+                    QuickSearchOutcome::QueryChanged
+                } else {
+                    QuickSearchOutcome::None
+                }
+            }
+            (Some(ch), _) if !ch.is_control() && self.query.chars().count() < QUERY_MAX => {
+                self.query.push(ch);
+                self.selected = 0;
+                QuickSearchOutcome::QueryChanged
+            }
+            _ => QuickSearchOutcome::None,
+        }
+    }
+
+    /// A click on a row opens it; a click outside the panel dismisses it.
+    pub fn click(&mut self, p: Point, bounds: Rect) -> QuickSearchOutcome {
+        let rect = self.rect(bounds);
+        if !rect.contains(p) {
+            return QuickSearchOutcome::Close;
+        }
+        match (0..self.results.len()).find(|&row| Self::row_rect(rect, row).contains(p)) {
+            Some(row) => {
+                self.selected = row;
+                QuickSearchOutcome::Open(row)
+            }
+            None => QuickSearchOutcome::None,
+        }
+    }
+
+    pub fn draw(&self, bounds: Rect, pointer: Point) {
+        let rect = self.rect(bounds);
+        fill(rect, 0x10283A);
+        fill(Rect::new(rect.x, rect.y, rect.width, 1), 0x7BB9E3);
+        fill(
+            Rect::new(rect.x, rect.y + rect.height as i32 - 1, rect.width, 1),
+            0x091521,
+        );
+
+        let field = Self::field_rect(rect);
+        fill(field, 0x0A1C2B);
+        fill(
+            Rect::new(field.x, field.y + field.height as i32 - 1, field.width, 1),
+            0x4F7A9D,
+        );
+        let cols = (field.width as usize).saturating_sub(16) / 6;
+        let ty = field.y + (field.height as i32 - 7) / 2;
+        if self.query.is_empty() {
+            text(field.x + 8, ty, "Buscar apps, ajustes y archivos...", 0x7C8FA6);
+        }
+        let shown = tail_to(&self.query, cols);
+        text(field.x + 8, ty, shown, 0xEAF6FF);
+        let caret_x = field.x + 8 + shown.chars().count() as i32 * 6;
+        fill(Rect::new(caret_x, field.y + 6, 1, field.height - 12), 0x8CC8F0);
+
+        let row_cols = (rect.width as usize).saturating_sub(2 * PAD as usize + 16) / 6;
+        for (row, entry) in self.results.iter().enumerate() {
+            let row_rect = Self::row_rect(rect, row);
+            let bg = if row == self.selected {
+                0x1A4F78
+            } else if row_rect.contains(pointer) {
+                0x17364F
+            } else {
+                0x10283A
+            };
+            fill(row_rect, bg);
+            text(
+                row_rect.x + 8,
+                row_rect.y + 6,
+                trim_to(&entry.label, row_cols),
+                0xEAF6FF,
+            );
+            text(
+                row_rect.x + 8,
+                row_rect.y + 18,
+                trim_to(&entry.subtitle, row_cols),
+                0x8FB3CF,
+            );
+        }
+
+        let status_y = rect.y + rect.height as i32 - STATUS_H as i32 + 4;
+        let status = if self.results.is_empty() && !self.query.trim().is_empty() {
+            "Sin resultados"
+        } else {
+            self.status.as_str()
+        };
+        text(rect.x + PAD, status_y, trim_to(status, row_cols), 0xA6C7E1);
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "search_overlay";
+
+    fn keys_edit_query_and_pick_rows() {
+        let entry = |label: &str| SearchResultEntry {
+            label: String::from(label),
+            subtitle: String::new(),
+            command: String::from("recent|app|calc"),
+        };
+        let mut search = QuickSearch::new();
+        crate::selftest::ensure_eq(search.key(Some('\n'), None), QuickSearchOutcome::None, "sin resultados")?;
+        crate::selftest::ensure_eq(search.key(Some('c'), None), QuickSearchOutcome::QueryChanged, "escribir")?;
+        search.set_results(alloc::vec![entry("Calculadora"), entry("Calendario")], "");
+        crate::selftest::ensure_eq(search.key(None, Some(SpecialKey::Down)), QuickSearchOutcome::None, "bajar")?;
+        crate::selftest::ensure_eq(search.key(None, Some(SpecialKey::Down)), QuickSearchOutcome::None, "tope")?;
+        crate::selftest::ensure_eq(search.key(Some('\n'), None), QuickSearchOutcome::Open(1), "abrir")?;
+        crate::selftest::ensure_eq(search.key(Some('\x08'), None), QuickSearchOutcome::QueryChanged, "borrar")?;
+        crate::selftest::ensure_eq(search.key(Some('\x08'), None), QuickSearchOutcome::None, "ya vacio")?;
+        crate::selftest::ensure_eq(search.key(Some('\x1b'), None), QuickSearchOutcome::Close, "escape")
+    }
+
+    fn clicks_open_rows_or_dismiss() {
+        let bounds = Rect::new(0, 0, 1024, 720);
+        let mut search = QuickSearch::new();
+        search.set_results(
+            alloc::vec![SearchResultEntry {
+                label: String::from("NOTAS.TXT"),
+                subtitle: String::from("/DOCS/"),
+                command: String::new(),
+            }],
+            "",
+        );
+        let row = QuickSearch::row_rect(search.rect(bounds), 0);
+        crate::selftest::ensure_eq(
+            search.click(Point { x: row.x + 5, y: row.y + 5 }, bounds),
+            QuickSearchOutcome::Open(0),
+            "fila",
+        )?;
+        crate::selftest::ensure_eq(search.click(Point { x: 2, y: 2 }, bounds), QuickSearchOutcome::Close, "fuera")
+    }
+}
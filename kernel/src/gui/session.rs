@@ -0,0 +1,281 @@
+//! Session restore: the windows open at a clean shutdown, reopened at the
+//! next boot.
+//!
+//! Each window is one config string `session.window.N` (bottom of the stack
+//! first) holding its workspace, state, normal geometry, the browser URL and
+//! last the relaunch command the compositor already keeps for the recent
+//! list (`recent|app|notepad`, `recent|file|...` for an editor's file).
+//! Windows without a relaunch command (dialogs, tool panels) are not saved.
+//!
+//! `session.restore` decides what happens at boot: `ask` (default) offers
+//! the session in a dialog, `always` reopens it straight away and `never`
+//! forgets it. Either way the saved session is dropped once used.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::Rect;
+use crate::config::ConfigValue;
+
+pub const RESTORE_KEY: &str = "session.restore";
+const WINDOW_PREFIX: &str = "session.window.";
+const FORMAT_TAG: &str = "v1";
+/// More windows than this are not worth a multi-second restore.
+pub const MAX_WINDOWS: usize = 16;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Ask,
+    Always,
+    Never,
+}
+
+impl Mode {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "ask" => Some(Mode::Ask),
+            "always" => Some(Mode::Always),
+            "never" => Some(Mode::Never),
+            _ => None,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Mode::Ask => "ask",
+            Mode::Always => "always",
+            Mode::Never => "never",
+        }
+    }
+}
+
+pub fn mode() -> Mode {
+    Mode::parse(crate::config::get_str(RESTORE_KEY, "ask").as_str()).unwrap_or(Mode::Ask)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum State {
+    Normal,
+    Minimized,
+    Maximized,
+}
+
+impl State {
+    const fn tag(self) -> char {
+        match self {
+            State::Normal => 'n',
+            State::Minimized => 'm',
+            State::Maximized => 'x',
+        }
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "n" => Some(State::Normal),
+            "m" => Some(State::Minimized),
+            "x" => Some(State::Maximized),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct SavedWindow {
+    /// 1-based, like `Window::desktop_id`.
+    pub desktop_id: u8,
+    pub state: State,
+    /// Geometry when not maximized.
+    pub rect: Rect,
+    /// Page of a browser window; empty for other apps.
+    pub url: String,
+    pub command: String,
+}
+
+impl SavedWindow {
+    /// `None` when it can't round-trip: a '|' in the URL or a value over
+    /// the config limit.
+    pub fn encode(&self) -> Option<String> {
+        if self.url.contains('|') || self.command.is_empty() {
+            return None;
+        }
+        let text = alloc::format!(
+            "{}|{}|{}|{}|{}|{}|{}|{}|{}",
+            FORMAT_TAG,
+            self.desktop_id,
+            self.state.tag(),
+            self.rect.x,
+            self.rect.y,
+            self.rect.width,
+            self.rect.height,
+            self.url,
+            self.command
+        );
+        (text.len() <= crate::config::VALUE_MAX_BYTES).then_some(text)
+    }
+
+    pub fn decode(text: &str) -> Option<Self> {
+        let mut parts = text.splitn(9, '|');
+        if parts.next()? != FORMAT_TAG {
+            return None;
+        }
+        let desktop_id = parts.next()?.parse::<u8>().ok().filter(|id| *id >= 1)?;
+        let state = State::from_tag(parts.next()?)?;
+        let x = parts.next()?.parse::<i32>().ok()?;
+        let y = parts.next()?.parse::<i32>().ok()?;
+        let width = parts.next()?.parse::<u32>().ok().filter(|w| *w > 0)?;
+        let height = parts.next()?.parse::<u32>().ok().filter(|h| *h > 0)?;
+        let url = String::from(parts.next()?);
+        let command = String::from(parts.next()?);
+        if command.is_empty() {
+            return None;
+        }
+        Some(Self {
+            desktop_id,
+            state,
+            rect: Rect::new(x, y, width, height),
+            url,
+            command,
+        })
+    }
+}
+
+fn window_key(index: usize) -> String {
+    alloc::format!("{}{}", WINDOW_PREFIX, index)
+}
+
+/// Replace the saved session with `windows`; returns how many were kept.
+pub fn save(windows: &[SavedWindow]) -> Result<usize, &'static str> {
+    clear()?;
+    let mut saved = 0;
+    for window in windows.iter().take(MAX_WINDOWS) {
+        let Some(text) = window.encode() else {
+            continue;
+        };
+        crate::config::set(window_key(saved).as_str(), ConfigValue::Str(text))?;
+        saved += 1;
+    }
+    Ok(saved)
+}
+
+/// The saved session in stacking order; damaged entries are skipped.
+pub fn load() -> Vec<SavedWindow> {
+    let mut entries: Vec<(usize, SavedWindow)> = crate::config::list(WINDOW_PREFIX)
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let index = key.strip_prefix(WINDOW_PREFIX)?.parse::<usize>().ok()?;
+            match value {
+                ConfigValue::Str(text) => SavedWindow::decode(text.as_str()).map(|window| (index, window)),
+                _ => None,
+            }
+        })
+        .collect();
+    entries.sort_by_key(|(index, _)| *index);
+    entries.into_iter().map(|(_, window)| window).collect()
+}
+
+pub fn clear() -> Result<usize, &'static str> {
+    let mut removed = 0;
+    for (key, _) in crate::config::list(WINDOW_PREFIX) {
+        if crate::config::unset(key.as_str())? {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// App name shown for a relaunch command ("notepad", "file", ...).
+pub fn describe(window: &SavedWindow) -> &str {
+    let mut parts = window.command.split('|').skip(1);
+    match (parts.next(), parts.next()) {
+        (Some("app"), Some(app)) => app,
+        (Some(category), _) => category,
+        _ => "?",
+    }
+}
+
+/// Shared implementation of `session [status|clear|restore ask|always|never]`.
+/// `session save` and `session load` need the windows and are handled by the
+/// compositor.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim();
+    let (sub, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    match sub {
+        "" | "status" => {
+            let windows = load();
+            let mut out = alloc::vec![
+                alloc::format!("Sesion guardada: {} ventanas", windows.len()),
+                alloc::format!("Restaurar al arrancar: {}", mode().name()),
+            ];
+            for window in windows.iter() {
+                out.push(alloc::format!(
+                    "  escritorio {}  {}  {}x{}{}",
+                    window.desktop_id,
+                    describe(window),
+                    window.rect.width,
+                    window.rect.height,
+                    if window.url.is_empty() {
+                        String::new()
+                    } else {
+                        alloc::format!("  {}", window.url)
+                    }
+                ));
+            }
+            out
+        }
+        "clear" => match clear() {
+            Ok(removed) => alloc::vec![alloc::format!("Sesion borrada ({} ventanas).", removed)],
+            Err(e) => alloc::vec![alloc::format!("session: {}", e)],
+        },
+        "restore" => {
+            let Some(wanted) = Mode::parse(rest) else {
+                return alloc::vec![String::from("Uso: session restore ask|always|never")];
+            };
+            match crate::config::set(RESTORE_KEY, ConfigValue::Str(String::from(wanted.name()))) {
+                Ok(()) => alloc::vec![alloc::format!("Restaurar al arrancar: {}", wanted.name())],
+                Err(e) => alloc::vec![alloc::format!("config: {}", e)],
+            }
+        }
+        "save" | "load" => alloc::vec![String::from("session: save y load solo desde el escritorio")],
+        _ => alloc::vec![String::from(
+            "Uso: session [status|save|load|clear|restore ask|always|never]"
+        )],
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "session";
+
+    fn windows_round_trip_through_config_text() {
+        let window = SavedWindow {
+            desktop_id: 2,
+            state: State::Maximized,
+            rect: Rect::new(-20, 40, 800, 500),
+            url: String::from("http://example.com/a?b=c"),
+            command: String::from("recent|app|browser"),
+        };
+        let text = window.encode().ok_or_else(|| String::from("codificar"))?;
+        crate::selftest::ensure_eq(
+            text.as_str(),
+            "v1|2|x|-20|40|800|500|http://example.com/a?b=c|recent|app|browser",
+            "texto",
+        )?;
+        crate::selftest::ensure_eq(SavedWindow::decode(text.as_str()), Some(window.clone()), "ida y vuelta")?;
+        crate::selftest::ensure_eq(describe(&window), "browser", "app")
+    }
+
+    fn damaged_or_oversized_entries_are_dropped() {
+        crate::selftest::ensure_eq(SavedWindow::decode("v2|1|n|0|0|10|10||recent|app|ide"), None, "version")?;
+        crate::selftest::ensure_eq(SavedWindow::decode("v1|0|n|0|0|10|10||recent|app|ide"), None, "escritorio 0")?;
+        crate::selftest::ensure_eq(SavedWindow::decode("v1|1|n|0|0|0|10||recent|app|ide"), None, "ancho 0")?;
+        crate::selftest::ensure_eq(SavedWindow::decode("v1|1|n|0|0|10|10|"), None, "sin comando")?;
+        let long = SavedWindow {
+            desktop_id: 1,
+            state: State::Normal,
+            rect: Rect::new(0, 0, 10, 10),
+            url: String::new(),
+            command: "x".repeat(crate::config::VALUE_MAX_BYTES),
+        };
+        crate::selftest::ensure_eq(long.encode(), None, "demasiado largo")?;
+        crate::selftest::ensure_eq(Mode::parse(" Always"), Some(Mode::Always), "modo")
+    }
+}
@@ -0,0 +1,349 @@
+//! Scrollable list/table. Only the rows in view are drawn, so long lists
+//! (directories, process tables, scan results) cost the same per frame as
+//! short ones. Mouse events are expected in the window-local coordinates
+//! `rect` is given in.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::Widget;
+use super::Window;
+use crate::gui::{Color, Event, Point, Rect, SpecialKey};
+
+const HEADER_H: u32 = 16;
+const SCROLLBAR_W: u32 = 6;
+const WHEEL_ROWS: usize = 3;
+
+pub struct Column {
+    pub title: String,
+    /// Pixels; 0 makes the column take what the others leave.
+    pub width: u32,
+}
+
+impl Column {
+    pub fn new(title: &str, width: u32) -> Self {
+        Self {
+            title: String::from(title),
+            width,
+        }
+    }
+}
+
+pub struct ListView {
+    pub rect: Rect,
+    /// Without columns there is no header and each row is one line of text.
+    pub columns: Vec<Column>,
+    rows: Vec<Vec<String>>,
+    selected: Option<usize>,
+    scroll: usize,
+    pub row_height: u32,
+    pub bg: Color,
+    pub text_color: Color,
+    pub selected_bg: Color,
+    pub header_bg: Color,
+}
+
+impl Default for ListView {
+    fn default() -> Self {
+        Self::new(Rect::new(0, 0, 0, 0), Vec::new())
+    }
+}
+
+impl ListView {
+    pub fn new(rect: Rect, columns: Vec<Column>) -> Self {
+        Self {
+            rect,
+            columns,
+            rows: Vec::new(),
+            selected: None,
+            scroll: 0,
+            row_height: 14,
+            bg: Color(0x0F172A),
+            text_color: Color(0xE2E8F0),
+            selected_bg: Color(0x1E40AF),
+            header_bg: Color(0x1F2937),
+        }
+    }
+
+    /// Replace the rows, keeping the selection and scroll where they still fit.
+    pub fn set_rows(&mut self, rows: Vec<Vec<String>>) {
+        self.rows = rows;
+        if self.selected.map(|s| s >= self.rows.len()).unwrap_or(false) {
+            self.selected = None;
+        }
+        self.scroll = self.scroll.min(self.max_scroll());
+    }
+
+    /// One-column convenience for plain text lines.
+    pub fn set_lines(&mut self, lines: &[String]) {
+        self.set_rows(lines.iter().map(|line| alloc::vec![line.clone()]).collect());
+    }
+
+    pub fn rows(&self) -> &[Vec<String>] {
+        &self.rows
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// First cell of `row`, which is the whole line for one-column lists.
+    pub fn text(&self, row: usize) -> Option<&str> {
+        self.rows
+            .get(row)
+            .and_then(|cells| cells.first())
+            .map(|cell| cell.as_str())
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    fn header_h(&self) -> u32 {
+        if self.columns.is_empty() {
+            0
+        } else {
+            HEADER_H
+        }
+    }
+
+    pub fn visible_rows(&self) -> usize {
+        let body = self.rect.height.saturating_sub(self.header_h() + 4);
+        (body / self.row_height.max(1)).max(1) as usize
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.rows.len().saturating_sub(self.visible_rows())
+    }
+
+    /// Select `row` (or nothing) and scroll it into view.
+    pub fn select(&mut self, row: Option<usize>) {
+        self.selected = row.filter(|&r| r < self.rows.len());
+        if let Some(row) = self.selected {
+            let visible = self.visible_rows();
+            if row < self.scroll {
+                self.scroll = row;
+            } else if row >= self.scroll + visible {
+                self.scroll = row + 1 - visible;
+            }
+        }
+    }
+
+    /// Move the selection by `delta` rows, starting at the top when nothing
+    /// is selected. Returns whether it changed.
+    pub fn move_selection(&mut self, delta: i32) -> bool {
+        if self.rows.is_empty() {
+            return false;
+        }
+        let last = self.rows.len() - 1;
+        let next = match self.selected {
+            None => 0,
+            Some(row) if delta < 0 => row.saturating_sub(delta.unsigned_abs() as usize),
+            Some(row) => (row + delta as usize).min(last),
+        };
+        let changed = self.selected != Some(next);
+        self.select(Some(next));
+        changed
+    }
+
+    /// Scroll by `delta` rows (negative is up). Returns whether it moved.
+    pub fn scroll_by(&mut self, delta: i32) -> bool {
+        let before = self.scroll;
+        if delta < 0 {
+            self.scroll = self.scroll.saturating_sub(delta.unsigned_abs() as usize);
+        } else {
+            self.scroll = (self.scroll + delta as usize).min(self.max_scroll());
+        }
+        self.scroll != before
+    }
+
+    pub fn row_rect(&self, visible_row: usize) -> Rect {
+        Rect::new(
+            self.rect.x + 2,
+            self.rect.y + 2 + (self.header_h() + visible_row as u32 * self.row_height) as i32,
+            self.rect.width.saturating_sub(4 + SCROLLBAR_W),
+            self.row_height,
+        )
+    }
+
+    pub fn row_at(&self, p: Point) -> Option<usize> {
+        if !self.rect.contains(p) {
+            return None;
+        }
+        let row = (0..self.visible_rows()).find(|&r| self.row_rect(r).contains(p))? + self.scroll;
+        (row < self.rows.len()).then_some(row)
+    }
+
+    /// Column whose header is under `p`, for click-to-sort.
+    pub fn column_at(&self, p: Point) -> Option<usize> {
+        if self.columns.is_empty() || !self.rect.contains(p) || p.y >= self.rect.y + 2 + HEADER_H as i32 {
+            return None;
+        }
+        let x = p.x - self.rect.x - 2;
+        self.column_spans(self.rect.width.saturating_sub(4 + SCROLLBAR_W))
+            .iter()
+            .position(|&(left, w)| x >= left && x < left + w as i32)
+    }
+
+    /// Left edge and width of every column inside `width` pixels.
+    fn column_spans(&self, width: u32) -> Vec<(i32, u32)> {
+        let fixed: u32 = self.columns.iter().map(|c| c.width).sum();
+        let flexible = self.columns.iter().filter(|c| c.width == 0).count().max(1) as u32;
+        let share = width.saturating_sub(fixed) / flexible;
+        let mut x = 0i32;
+        let mut spans = Vec::with_capacity(self.columns.len().max(1));
+        if self.columns.is_empty() {
+            spans.push((0, width));
+        }
+        for column in self.columns.iter() {
+            let w = if column.width == 0 { share } else { column.width };
+            spans.push((x, w));
+            x += w as i32;
+        }
+        spans
+    }
+
+    fn draw_cell(window: &mut Window, x: i32, y: i32, width: u32, text: &str, color: Color) {
+        let max_chars = (width.saturating_sub(8) / 6) as usize;
+        let end = text.char_indices().nth(max_chars).map(|(i, _)| i).unwrap_or(text.len());
+        window.draw_text(x.max(0) as u32, y.max(0) as u32, &text.as_bytes()[..end], color);
+    }
+}
+
+impl Widget for ListView {
+    fn draw(&self, window: &mut Window, rect: Rect) {
+        window.fill_rect(rect, self.bg);
+        window.draw_border(rect, Color(0x334155));
+        let inner_w = rect.width.saturating_sub(4 + SCROLLBAR_W);
+        let spans = self.column_spans(inner_w);
+
+        if !self.columns.is_empty() {
+            window.fill_rect(
+                Rect::new(rect.x + 1, rect.y + 1, rect.width.saturating_sub(2), HEADER_H),
+                self.header_bg,
+            );
+            for (column, (x, w)) in self.columns.iter().zip(spans.iter()) {
+                Self::draw_cell(
+                    window,
+                    rect.x + 2 + x + 4,
+                    rect.y + 5,
+                    *w,
+                    column.title.as_str(),
+                    Color(0x9CA3AF),
+                );
+            }
+        }
+
+        let text_dy = (self.row_height as i32 - 7) / 2;
+        for visible_row in 0..self.visible_rows() {
+            let row = self.scroll + visible_row;
+            let Some(cells) = self.rows.get(row) else {
+                break;
+            };
+            let row_rect = self.row_rect(visible_row);
+            let row_rect = Rect::new(
+                rect.x + (row_rect.x - self.rect.x),
+                rect.y + (row_rect.y - self.rect.y),
+                inner_w,
+                self.row_height,
+            );
+            if self.selected == Some(row) {
+                window.fill_rect(row_rect, self.selected_bg);
+            }
+            for (cell, (x, w)) in cells.iter().zip(spans.iter()) {
+                Self::draw_cell(
+                    window,
+                    row_rect.x + x + 4,
+                    row_rect.y + text_dy,
+                    *w,
+                    cell.as_str(),
+                    self.text_color,
+                );
+            }
+        }
+
+        // Scrollbar thumb sized to the visible share of the rows.
+        let visible = self.visible_rows();
+        if self.rows.len() > visible {
+            let track_y = rect.y + 2 + self.header_h() as i32;
+            let track_h = rect.height.saturating_sub(self.header_h() + 4);
+            let thumb_h = (track_h as usize * visible / self.rows.len()).max(8) as u32;
+            let travel = track_h.saturating_sub(thumb_h) as usize;
+            let thumb_y = track_y + (travel * self.scroll / self.max_scroll().max(1)) as i32;
+            let track_x = rect.x + rect.width as i32 - SCROLLBAR_W as i32 - 2;
+            window.fill_rect(Rect::new(track_x, track_y, SCROLLBAR_W, track_h), Color(0x1E293B));
+            window.fill_rect(Rect::new(track_x, thumb_y, SCROLLBAR_W, thumb_h), Color(0x64748B));
+        }
+    }
+
+    /// Up/Down move the selection, the wheel scrolls and a click selects.
+    fn handle_event(&mut self, event: Event) -> bool {
+        match event {
+            Event::Keyboard(k) if k.down => match k.special {
+                Some(SpecialKey::Up) => self.move_selection(-1),
+                Some(SpecialKey::Down) => self.move_selection(1),
+                _ => false,
+            },
+            Event::Mouse(m) => {
+                let p = Point { x: m.x, y: m.y };
+                if m.wheel_delta != 0 && self.rect.contains(p) {
+                    let rows = WHEEL_ROWS as i32;
+                    return self.scroll_by(if m.wheel_delta > 0 { -rows } else { rows });
+                }
+                match self.row_at(p) {
+                    Some(row) if m.left_down => {
+                        let changed = self.selected != Some(row);
+                        self.select(Some(row));
+                        changed
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "widget_list";
+
+    fn selection_follows_keys_and_stays_visible() {
+        let mut list = ListView::new(Rect::new(0, 0, 200, 4 + 14 * 3), Vec::new());
+        let lines: Vec<String> = (0..10).map(|i| alloc::format!("fila {}", i)).collect();
+        list.set_lines(&lines);
+        crate::selftest::ensure_eq(list.visible_rows(), 3, "filas visibles")?;
+        crate::selftest::ensure(list.move_selection(1), "primera seleccion")?;
+        crate::selftest::ensure_eq(list.selected(), Some(0), "empieza arriba")?;
+        for _ in 0..4 {
+            list.move_selection(1);
+        }
+        crate::selftest::ensure_eq(list.selected(), Some(4), "baja")?;
+        crate::selftest::ensure_eq(list.scroll(), 2, "sigue a la seleccion")?;
+        list.move_selection(-10);
+        crate::selftest::ensure_eq((list.selected(), list.scroll()), (Some(0), 0), "vuelve arriba")?;
+        crate::selftest::ensure(!list.scroll_by(-1), "no pasa del inicio")?;
+        list.scroll_by(100);
+        crate::selftest::ensure_eq(list.scroll(), 7, "tope de desplazamiento")
+    }
+
+    fn rows_hit_test_and_shrink() {
+        let mut list = ListView::new(Rect::new(10, 20, 200, 4 + 16 + 14 * 4), alloc::vec![Column::new("PID", 40), Column::new("Nombre", 0)]);
+        let rows: Vec<Vec<String>> = (0..6).map(|i| alloc::vec![alloc::format!("{}", i), String::from("x")]).collect();
+        list.set_rows(rows);
+        crate::selftest::ensure_eq(list.row_at(Point { x: 20, y: 20 + 2 + 16 + 14 + 1 }), Some(1), "segunda fila")?;
+        crate::selftest::ensure_eq(list.row_at(Point { x: 20, y: 25 }), None, "cabecera")?;
+        crate::selftest::ensure_eq(list.column_at(Point { x: 10 + 2 + 50, y: 25 }), Some(1), "columna")?;
+        list.select(Some(5));
+        list.set_rows(alloc::vec![alloc::vec![String::from("1"), String::from("y")]]);
+        crate::selftest::ensure_eq((list.selected(), list.scroll()), (None, 0), "se ajusta al encoger")?;
+        crate::selftest::ensure_eq(list.column_spans(180), alloc::vec![(0, 40), (40, 140)], "columnas")
+    }
+}
@@ -9,3 +9,5 @@ pub trait Widget {
 pub mod terminal;
 pub mod button;
 pub mod taskbar;
+pub mod list;
+pub mod text_area;
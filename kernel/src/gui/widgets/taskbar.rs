@@ -17,12 +17,14 @@ pub const PINNED_GAP: i32 = 2;
 pub const PINNED_ARROW_W: i32 = 16;
 /// Width of the settings gear icon area.
 pub const SETTINGS_ICON_W: i32 = 28;
+/// Width of the notification bell area.
+pub const NOTIFY_ICON_W: i32 = 28;
 /// Width of the clock area.
 pub const CLOCK_W: i32 = 80;
-/// Total right-side area (arrows + 6 icons + settings + clock + padding).
+/// Total right-side area (arrows + 6 icons + settings + bell + clock + padding).
 pub const TRAY_TOTAL_W: i32 =
     PINNED_ARROW_W + PINNED_VISIBLE as i32 * (PINNED_ICON_SIZE + PINNED_GAP) + PINNED_ARROW_W
-    + 4 + SETTINGS_ICON_W + 4 + CLOCK_W + 4;
+    + 4 + SETTINGS_ICON_W + 4 + NOTIFY_ICON_W + 4 + CLOCK_W + 4;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum PinnedItemKind {
@@ -56,6 +58,8 @@ pub struct Taskbar {
     pub pinned_scroll: usize,
     /// Index of pinned item currently hovered (-1 = none).
     pub pinned_hover_index: i32,
+    /// Bar color (config key `desktop.taskbar`).
+    pub background: u32,
 }
 
 impl Taskbar {
@@ -70,6 +74,7 @@ impl Taskbar {
             pinned_items: Vec::new(),
             pinned_scroll: 0,
             pinned_hover_index: -1,
+            background: 0x222222,
         }
     }
 
@@ -134,7 +139,7 @@ impl Taskbar {
 impl Widget for Taskbar {
     fn draw(&self, window: &mut Window, _rect: Rect) {
         // Draw Taskbar Background
-        let bg_color = Color(0x222222);
+        let bg_color = Color(self.background);
         window.fill_rect(Rect::new(0, 0, self.rect.width, self.rect.height), bg_color);
 
         // Draw Top Border
@@ -0,0 +1,457 @@
+//! Multi-line editable text with word-wrap, a caret and a selection.
+//! Positions are byte offsets into `text`; layout is recomputed from the
+//! text on demand, which is cheap at notepad sizes and keeps no stale state.
+//! Mouse events are expected in the window-local coordinates `rect` is in.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::Widget;
+use super::Window;
+use crate::gui::{Color, Event, Point, Rect, SpecialKey};
+
+const CHAR_W: i32 = 6;
+const LINE_H: i32 = 9;
+const PAD: i32 = 4;
+const WHEEL_LINES: i32 = 3;
+
+pub struct TextArea {
+    pub rect: Rect,
+    text: String,
+    cursor: usize,
+    /// Other end of the selection; the selection is anchor..cursor.
+    anchor: Option<usize>,
+    scroll: usize,
+    dragging: bool,
+    /// 0 means unlimited.
+    pub max_len: usize,
+    pub focused: bool,
+    pub bg: Color,
+    pub text_color: Color,
+    pub selection_bg: Color,
+    pub border: Color,
+}
+
+impl Default for TextArea {
+    fn default() -> Self {
+        Self::new(Rect::new(0, 0, 0, 0))
+    }
+}
+
+impl TextArea {
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect,
+            text: String::new(),
+            cursor: 0,
+            anchor: None,
+            scroll: 0,
+            dragging: false,
+            max_len: 0,
+            focused: true,
+            bg: Color(0xFFFFFF),
+            text_color: Color(0x182736),
+            selection_bg: Color(0xB7D4F5),
+            border: Color(0xAFC0D3),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        self.text.as_str()
+    }
+
+    /// Replace the contents; the caret goes to the start.
+    pub fn set_text(&mut self, text: &str) {
+        self.text.clear();
+        self.text.extend(text.chars().filter(|&ch| ch != '\r'));
+        self.cursor = 0;
+        self.anchor = None;
+        self.scroll = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text("");
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn set_cursor(&mut self, offset: usize) {
+        self.cursor = self.clamp_offset(offset);
+        self.anchor = None;
+        self.ensure_cursor_visible();
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.cursor = self.text.len();
+        self.ensure_cursor_visible();
+    }
+
+    /// Start and end of the selection, or None when it is empty.
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        let anchor = self.anchor?;
+        (anchor != self.cursor).then(|| (anchor.min(self.cursor), anchor.max(self.cursor)))
+    }
+
+    pub fn selected_text(&self) -> Option<&str> {
+        self.selection().map(|(a, b)| &self.text[a..b])
+    }
+
+    fn clamp_offset(&self, offset: usize) -> usize {
+        let mut offset = offset.min(self.text.len());
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+
+    fn delete_selection(&mut self) -> bool {
+        let Some((a, b)) = self.selection() else {
+            self.anchor = None;
+            return false;
+        };
+        self.text.replace_range(a..b, "");
+        self.cursor = a;
+        self.anchor = None;
+        true
+    }
+
+    /// Type `text` at the caret, replacing the selection. Control characters
+    /// other than newline and tab are dropped. Returns whether anything changed.
+    pub fn insert_str(&mut self, text: &str) -> bool {
+        let mut changed = self.delete_selection();
+        for ch in text.chars() {
+            if ch.is_control() && ch != '\n' && ch != '\t' {
+                continue;
+            }
+            if self.max_len != 0 && self.text.len() + ch.len_utf8() > self.max_len {
+                break;
+            }
+            self.text.insert(self.cursor, ch);
+            self.cursor += ch.len_utf8();
+            changed = true;
+        }
+        self.ensure_cursor_visible();
+        changed
+    }
+
+    pub fn backspace(&mut self) -> bool {
+        if !self.delete_selection() {
+            let Some(ch) = self.text[..self.cursor].chars().next_back() else {
+                return false;
+            };
+            self.cursor -= ch.len_utf8();
+            self.text.remove(self.cursor);
+        }
+        self.ensure_cursor_visible();
+        true
+    }
+
+    fn columns(&self) -> usize {
+        ((self.rect.width as i32 - PAD * 2) / CHAR_W).max(1) as usize
+    }
+
+    pub fn visible_lines(&self) -> usize {
+        ((self.rect.height as i32 - PAD * 2) / LINE_H).max(1) as usize
+    }
+
+    /// Byte ranges of the wrapped lines. A wrapped line keeps its trailing
+    /// space; a line ending in a newline stops before it.
+    pub fn layout(&self) -> Vec<(usize, usize)> {
+        let cols = self.columns();
+        let mut lines = Vec::new();
+        let mut line_start = 0;
+        for logical in self.text.split('\n') {
+            let end = line_start + logical.len();
+            let mut start = line_start;
+            loop {
+                let rest = &self.text[start..end];
+                let Some((cut, _)) = rest.char_indices().nth(cols) else {
+                    lines.push((start, end));
+                    break;
+                };
+                // Prefer breaking after the last space that fits.
+                let split = match rest[..cut].rfind(' ') {
+                    Some(space) if space > 0 => space + 1,
+                    _ => cut,
+                };
+                lines.push((start, start + split));
+                start += split;
+            }
+            line_start = end + 1;
+        }
+        lines
+    }
+
+    /// Wrapped line and column of `offset`.
+    fn position_of(lines: &[(usize, usize)], text: &str, offset: usize) -> (usize, usize) {
+        let line = lines.iter().rposition(|&(start, _)| start <= offset).unwrap_or(0);
+        let (start, end) = lines[line];
+        (line, text[start..offset.min(end)].chars().count())
+    }
+
+    /// Offset of `col` on wrapped `line`. The end of a wrapped line is the
+    /// start of the next one, so the caret stops one character short there.
+    fn offset_of(&self, lines: &[(usize, usize)], line: usize, col: usize) -> usize {
+        let (start, end) = lines[line];
+        let wrapped = lines.get(line + 1).map(|&(next, _)| next == end).unwrap_or(false);
+        let segment = &self.text[start..end];
+        let mut max_col = segment.chars().count();
+        if wrapped {
+            max_col = max_col.saturating_sub(1);
+        }
+        start
+            + segment
+                .char_indices()
+                .nth(col.min(max_col))
+                .map(|(i, _)| i)
+                .unwrap_or(segment.len())
+    }
+
+    pub fn cursor_position(&self) -> (usize, usize) {
+        Self::position_of(&self.layout(), self.text.as_str(), self.cursor)
+    }
+
+    fn ensure_cursor_visible(&mut self) {
+        let lines = self.layout();
+        let (line, _) = Self::position_of(&lines, self.text.as_str(), self.cursor);
+        let visible = self.visible_lines();
+        if line < self.scroll {
+            self.scroll = line;
+        } else if line >= self.scroll + visible {
+            self.scroll = line + 1 - visible;
+        }
+        self.scroll = self.scroll.min(lines.len().saturating_sub(visible));
+    }
+
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    /// Scroll by `delta` wrapped lines (negative is up). Returns whether it moved.
+    pub fn scroll_by(&mut self, delta: i32) -> bool {
+        let before = self.scroll;
+        let max = self.layout().len().saturating_sub(self.visible_lines());
+        if delta < 0 {
+            self.scroll = self.scroll.saturating_sub(delta.unsigned_abs() as usize);
+        } else {
+            self.scroll = (self.scroll + delta as usize).min(max);
+        }
+        self.scroll != before
+    }
+
+    /// Move the caret one character or line, dropping the selection.
+    pub fn move_cursor(&mut self, key: SpecialKey) -> bool {
+        let before = (self.cursor, self.anchor);
+        if let Some((a, b)) = self.selection() {
+            // Left/Right collapse the selection to the matching end.
+            match key {
+                SpecialKey::Left => self.cursor = a,
+                SpecialKey::Right => self.cursor = b,
+                _ => {}
+            }
+        } else {
+            match key {
+                SpecialKey::Left => {
+                    if let Some(ch) = self.text[..self.cursor].chars().next_back() {
+                        self.cursor -= ch.len_utf8();
+                    }
+                }
+                SpecialKey::Right => {
+                    if let Some(ch) = self.text[self.cursor..].chars().next() {
+                        self.cursor += ch.len_utf8();
+                    }
+                }
+                SpecialKey::Up | SpecialKey::Down => {
+                    let lines = self.layout();
+                    let (line, col) = Self::position_of(&lines, self.text.as_str(), self.cursor);
+                    let target = if key == SpecialKey::Up {
+                        line.checked_sub(1)
+                    } else {
+                        (line + 1 < lines.len()).then_some(line + 1)
+                    };
+                    if let Some(target) = target {
+                        self.cursor = self.offset_of(&lines, target, col);
+                    }
+                }
+            }
+        }
+        self.anchor = None;
+        self.ensure_cursor_visible();
+        (self.cursor, self.anchor) != before
+    }
+
+    /// Text offset under window-local point `p`, clamped to the area.
+    pub fn offset_at(&self, p: Point) -> usize {
+        let lines = self.layout();
+        let row = ((p.y - self.rect.y - PAD).max(0) / LINE_H) as usize + self.scroll;
+        let col = ((p.x - self.rect.x - PAD).max(0) + CHAR_W / 2) / CHAR_W;
+        self.offset_of(&lines, row.min(lines.len() - 1), col as usize)
+    }
+
+    /// Mouse press: place the caret and start a drag selection.
+    pub fn press(&mut self, p: Point) {
+        self.cursor = self.offset_at(p);
+        self.anchor = Some(self.cursor);
+        self.dragging = true;
+    }
+
+    /// Mouse move with the button held: extend the selection.
+    pub fn drag(&mut self, p: Point) -> bool {
+        if !self.dragging {
+            return false;
+        }
+        let offset = self.offset_at(p);
+        if offset == self.cursor {
+            return false;
+        }
+        self.cursor = offset;
+        self.ensure_cursor_visible();
+        true
+    }
+
+    pub fn release(&mut self) {
+        self.dragging = false;
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    /// Keys from the GUI keyboard path: printable characters, Enter, Tab,
+    /// Backspace and the arrows. Returns whether the text or caret changed.
+    pub fn key(&mut self, key: Option<char>, special: Option<SpecialKey>) -> bool {
+        if let Some(special) = special {
+            return self.move_cursor(special);
+        }
+        match key {
+            Some('\x08') | Some('\x7f') => self.backspace(),
+            Some('\r') | Some('\n') => self.insert_str("\n"),
+            Some(ch) if ch == '\t' || !ch.is_control() => {
+                let mut buf = [0u8; 4];
+                self.insert_str(ch.encode_utf8(&mut buf))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Widget for TextArea {
+    fn draw(&self, window: &mut Window, rect: Rect) {
+        window.fill_rect(rect, self.bg);
+        window.draw_border(rect, self.border);
+        let lines = self.layout();
+        let selection = self.selection();
+        let text_x = rect.x + PAD;
+        for (row, &(start, end)) in lines.iter().skip(self.scroll).take(self.visible_lines()).enumerate() {
+            let y = rect.y + PAD + row as i32 * LINE_H;
+            if let Some((a, b)) = selection {
+                let (from, to) = (a.max(start), b.min(end));
+                if from < to {
+                    let x0 = self.text[start..from].chars().count() as i32 * CHAR_W;
+                    let w = self.text[from..to].chars().count() as i32 * CHAR_W;
+                    window.fill_rect(
+                        Rect::new(text_x + x0, y - 1, w as u32, LINE_H as u32),
+                        self.selection_bg,
+                    );
+                }
+            }
+            let shown: String = self.text[start..end]
+                .chars()
+                .map(|ch| if ch == '\t' { ' ' } else { ch })
+                .collect();
+            window.draw_text(text_x.max(0) as u32, y.max(0) as u32, shown.as_bytes(), self.text_color);
+        }
+
+        if self.focused {
+            let (line, col) = Self::position_of(&lines, self.text.as_str(), self.cursor);
+            if line >= self.scroll && line < self.scroll + self.visible_lines() {
+                let x = (text_x + col as i32 * CHAR_W).min(rect.x + rect.width as i32 - PAD);
+                let y = rect.y + PAD + (line - self.scroll) as i32 * LINE_H - 1;
+                window.fill_rect(Rect::new(x, y, 1, LINE_H as u32), self.text_color);
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: Event) -> bool {
+        match event {
+            Event::Keyboard(k) if k.down => self.key(k.key, k.special),
+            Event::Mouse(m) => {
+                let p = Point { x: m.x, y: m.y };
+                if m.wheel_delta != 0 && self.rect.contains(p) {
+                    return self.scroll_by(if m.wheel_delta > 0 { -WHEEL_LINES } else { WHEEL_LINES });
+                }
+                if !m.left_down {
+                    let was = self.dragging;
+                    self.release();
+                    return was;
+                }
+                if self.dragging {
+                    self.drag(p)
+                } else if self.rect.contains(p) {
+                    self.press(p);
+                    true
+                } else {
+                    false
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "widget_text_area";
+
+    fn wraps_at_spaces_and_newlines() {
+        // 8 columns: (4 + 48 + 4) px wide.
+        let mut area = TextArea::new(Rect::new(0, 0, 56, 8 + 9 * 3));
+        area.set_text("hola mundo cruel\nabcdefghijk");
+        let lines = area.layout();
+        let shown: Vec<&str> = lines.iter().map(|&(a, b)| &area.text()[a..b]).collect();
+        crate::selftest::ensure_eq(shown, alloc::vec!["hola ", "mundo ", "cruel", "abcdefgh", "ijk"], "ajuste")?;
+        area.set_text("");
+        crate::selftest::ensure_eq(area.layout(), alloc::vec![(0, 0)], "vacio")
+    }
+
+    fn caret_moves_by_char_and_line() {
+        let mut area = TextArea::new(Rect::new(0, 0, 56, 8 + 9 * 2));
+        area.set_text("hola mundo\nx");
+        area.set_cursor(2);
+        area.move_cursor(SpecialKey::Down);
+        // "mundo " is the second wrapped line; column 2 lands on 'n'.
+        crate::selftest::ensure_eq(area.cursor(), 7, "baja")?;
+        area.move_cursor(SpecialKey::Down);
+        crate::selftest::ensure_eq((area.cursor(), area.scroll()), (12, 1), "baja y desplaza")?;
+        area.move_cursor(SpecialKey::Left);
+        area.move_cursor(SpecialKey::Left);
+        crate::selftest::ensure_eq(area.cursor(), 10, "izquierda cruza la linea")?;
+        area.move_cursor(SpecialKey::Up);
+        area.move_cursor(SpecialKey::Up);
+        // Column 5 does not exist on the wrapped "hola ": the caret stops before its space.
+        crate::selftest::ensure_eq(area.cursor_position(), (0, 4), "sube a la primera")
+    }
+
+    fn selection_replace_and_backspace() {
+        let mut area = TextArea::new(Rect::new(10, 10, 200, 60));
+        area.set_text("abc def");
+        area.press(Point { x: 10 + 4 + 4 * 6, y: 15 });
+        area.drag(Point { x: 10 + 4 + 7 * 6, y: 15 });
+        area.release();
+        crate::selftest::ensure_eq(area.selected_text(), Some("def"), "arrastre")?;
+        area.insert_str("xy\r\x01z");
+        crate::selftest::ensure_eq(area.text(), "abc xyz", "reemplaza")?;
+        area.select_all();
+        area.backspace();
+        crate::selftest::ensure(area.is_empty(), "borra la seleccion")?;
+        area.max_len = 3;
+        area.insert_str("12345");
+        crate::selftest::ensure_eq(area.text(), "123", "limite")
+    }
+}
@@ -2,6 +2,12 @@ use alloc::string::String;
 use alloc::vec::Vec;
 
 use super::{Color, Rect, SpecialKey};
+use super::clipboard::PayloadKind;
+use super::interaction::{DragEvent, MenuItem};
+use super::widgets::button::Button;
+use super::widgets::list::{Column, ListView};
+use super::widgets::text_area::TextArea;
+use super::widgets::Widget;
 
 pub const TITLE_BAR_H: i32 = 22;
 pub const WINDOW_TITLE_BAR_H: i32 = TITLE_BAR_H;
@@ -12,6 +18,12 @@ const TERMINAL_LINE_HEIGHT: i32 = 12;
 const TERMINAL_HISTORY_MAX_LINES: usize = 4096;
 const TERMINAL_TEXT_X: usize = 10;
 const TERMINAL_CHAR_W: usize = 6;
+// Actions of the generic context menu (`context_menu_items`).
+const CONTEXT_COPY_ALL: u32 = 1;
+const CONTEXT_PASTE: u32 = 2;
+const CONTEXT_CLEAR: u32 = 3;
+const CONTEXT_COPY_LAST_LINE: u32 = 4;
+const CONTEXT_SELECT_ALL: u32 = 5;
 
 pub const EXPLORER_TOP_H: i32 = 30;
 const EXPLORER_STATUS_H: i32 = 58;
@@ -25,6 +37,15 @@ const EXPLORER_SEARCH_FIELD_MIN_W: i32 = 96;
 const EXPLORER_SEARCH_FIELD_MAX_W: i32 = 220;
 const EXPLORER_SEARCH_BUTTON_W: i32 = 62;
 
+// Display section of the Settings window (content coordinates). The
+// compositor hit-tests the same rows in `handle_settings_click`.
+pub const SETTINGS_GAMMA_ROW_Y: i32 = 75;
+pub const SETTINGS_TEMPERATURE_ROW_Y: i32 = 89;
+pub const SETTINGS_NIGHT_ROW_Y: i32 = 103;
+pub const SETTINGS_ROW_H: i32 = 12;
+pub const SETTINGS_SLIDER_X: i32 = 250;
+pub const SETTINGS_SLIDER_W: i32 = 200;
+
 const NOTEPAD_TOP_H: i32 = 36;
 const NOTEPAD_STATUS_H: i32 = 28;
 const SEARCH_TOP_H: i32 = 44;
@@ -35,6 +56,23 @@ const BROWSER_TOP_H: i32 = 64;
 const BROWSER_STATUS_H: i32 = 24;
 const IMAGE_VIEWER_TOP_H: i32 = 52;
 const IMAGE_VIEWER_STATUS_H: i32 = 28;
+const PDF_VIEWER_TOOLBAR_H: i32 = 36;
+const PDF_VIEWER_STATUS_H: i32 = 22;
+/// Gap around the page inside the viewport.
+const PDF_VIEWER_MARGIN: i32 = 8;
+/// Pixels moved per wheel notch or arrow key.
+const PDF_VIEWER_SCROLL_STEP: i32 = 40;
+const PDF_VIEWER_ZOOMS: [u32; 8] = [25, 50, 75, 100, 125, 150, 200, 300];
+const CALCULATOR_DISPLAY_H: i32 = 64;
+const CALCULATOR_KEY_H: i32 = 34;
+const CALCULATOR_KEY_GAP: i32 = 6;
+const CALENDAR_HEADER_H: i32 = 40;
+const CALENDAR_CELL_H: i32 = 30;
+const CALENDAR_FOOTER_H: i32 = 60;
+const CLOCK_HEADER_H: i32 = 76;
+const CLOCK_FOOTER_H: i32 = 64;
+const RECORDER_METER_Y: i32 = 92;
+const RECORDER_FOOTER_H: i32 = 64;
 const APP_RUNNER_TOP_H: i32 = 52;
 const APP_RUNNER_STATUS_H: i32 = 28;
 const IDE_STUDIO_TOP_H: i32 = 62;
@@ -157,8 +195,22 @@ const LINUX_BRIDGE_STATUS_H: i32 = 28;
 const IDE_STUDIO_MAX_TEXT_BYTES: usize = 128 * 1024;
 const IDE_STUDIO_UNDO_STACK_LIMIT: usize = 256;
 const TASK_MGR_HEADER_H: i32 = 42;
+const TASK_MGR_GRAPH_H: i32 = 74;
 const TASK_MGR_FOOTER_H: i32 = 68;
-const TASK_MGR_ROW_H: i32 = 18;
+const ABOUT_PC_HEADER_H: i32 = 42;
+const BOOT_ENTRIES_HEADER_H: i32 = 42;
+const BOOT_ENTRIES_PREVIEW_H: i32 = 84;
+const BOOT_ENTRIES_FOOTER_H: i32 = 68;
+const ABOUT_PC_ROW_H: i32 = 16;
+const MAIL_TOOLBAR_H: i32 = 36;
+const MAIL_STATUS_H: i32 = 22;
+const MAIL_ROW_H: i32 = 30;
+const MAIL_LINE_H: i32 = 12;
+const MAIL_FIELD_H: i32 = 22;
+const MAIL_FIELD_SPACING: i32 = 30;
+const MAIL_BUTTON_W: i32 = 96;
+const MAIL_FIELD_MAX_LEN: usize = 200;
+const MAIL_BODY_MAX_LEN: usize = 64 * 1024;
 
 #[derive(Copy, Clone, PartialEq)]
 pub enum WindowState {
@@ -185,6 +237,14 @@ pub enum WindowKind {
     WifiManager,
     TaskManager,
     VideoPlayer,
+    AboutPc,
+    Mail,
+    BootEntries,
+    PdfViewer,
+    Calculator,
+    Calendar,
+    Clock,
+    Recorder,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -255,8 +315,10 @@ impl ExplorerItem {
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum NotepadClickAction {
     New,
+    Open,
     Save,
     Delete,
+    Print,
     FilenameField,
     EditorArea,
 }
@@ -277,12 +339,94 @@ pub enum ExplorerSearchClickAction {
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum TaskManagerClickAction {
     Select(usize),
+    Sort(crate::sysmon::SortKey),
+    Kill,
     CancelInstall,
     CancelFs,
     CancelPaste,
     CancelAll,
 }
 
+/// What a Task Manager row stands for, so the selection survives refreshes
+/// and "Terminar" knows what to stop.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TaskManagerTarget {
+    Process(u16),
+    Install,
+    FsTask,
+    Paste,
+    LinuxRunloop,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BootEntriesClickAction {
+    Select(usize),
+    Up,
+    Down,
+    Toggle,
+    Delete,
+    Next,
+    Apply,
+    Discard,
+    Restore,
+    Reload,
+    Clean,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PdfViewerClickAction {
+    Prev,
+    Next,
+    ZoomOut,
+    ZoomIn,
+    Fit,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CalendarClickAction {
+    PrevMonth,
+    NextMonth,
+    Today,
+    Day(u8),
+    Event(usize),
+    Delete,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ClockClickAction {
+    /// Start a countdown of this many milliseconds.
+    Timer(u64),
+    Toggle,
+    Delete,
+    Select(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RecorderClickAction {
+    Record,
+    Stop,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MailView {
+    Inbox,
+    Compose,
+    Account,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MailClickAction {
+    Refresh,
+    Compose,
+    Reply,
+    Account,
+    Send,
+    SaveAccount,
+    Cancel,
+    Select(usize),
+    Field(usize),
+}
+
 #[derive(Clone)]
 pub struct SearchResultEntry {
     pub label: String,
@@ -360,6 +504,8 @@ struct IdeEditorSnapshot {
 pub struct Window {
     pub id: usize,
     pub desktop_id: u8,
+    /// Percent; below 100 only with `desktop.effects` on.
+    pub opacity: u8,
     pub rect: Rect,
     pub title: String,
     pub buffer: Vec<u32>,
@@ -394,7 +540,7 @@ pub struct Window {
 
     // Notepad state
     pub notepad_file_name: String,
-    pub notepad_text: String,
+    pub notepad_area: TextArea,
     pub notepad_status: String,
     pub notepad_dir_cluster: u32,
     pub notepad_dir_path: String,
@@ -423,6 +569,38 @@ pub struct Window {
     pub image_viewer_height: u32,
     pub image_viewer_pixels: Vec<u32>,
 
+    // PDF viewer state: the parsed document and the page on screen
+    pub pdf_viewer_file_name: String,
+    pub pdf_viewer_status: String,
+    pub pdf_viewer_document: Option<crate::pdf::Document>,
+    pub pdf_viewer_page: usize,
+    pub pdf_viewer_zoom: u32,
+    /// Zoom follows the window width until +/- picks a fixed step.
+    pub pdf_viewer_fit_width: bool,
+    pub pdf_viewer_rendered: Option<crate::pdf::RenderedPage>,
+    pub pdf_viewer_scroll_x: i32,
+    pub pdf_viewer_scroll_y: i32,
+
+    // Calculator, calendar and clock applets
+    pub calculator: crate::calc::Calculator,
+    pub calculator_history: ListView,
+    pub calendar_selected: crate::calendar::Date,
+    pub calendar_events: ListView,
+    /// "HH:MM texto" typed for a new event on the selected day.
+    pub calendar_input: String,
+    pub calendar_status: String,
+    /// Alarms first, then running timers.
+    pub clock_list: ListView,
+    pub clock_status: String,
+    /// Local second on screen, so the face is redrawn once per second.
+    pub clock_shown_second: i64,
+    pub recorder_status: String,
+    /// Elapsed second and level on screen, so the recorder only redraws
+    /// when one of them changes.
+    pub recorder_shown: (u64, i32),
+    /// Input level on the Settings meter when it was last drawn.
+    pub settings_shown_level: Option<i32>,
+
     // App Runner state
     pub app_runner_source_file: String,
     pub app_runner_rml_source: String,
@@ -537,10 +715,45 @@ pub struct Window {
     pub wifi_mode_active: bool,
 
     // Task Manager state
-    pub task_manager_lines: Vec<String>,
-    pub task_manager_scroll: usize,
-    pub task_manager_selected: Option<usize>,
+    pub task_manager_list: ListView,
+    /// One per list row, in display order.
+    pub task_manager_targets: Vec<TaskManagerTarget>,
+    pub task_manager_processes: Vec<crate::sysmon::ProcessRow>,
+    /// Desktop jobs (install, FS, paste...) listed after the processes.
+    pub task_manager_jobs: Vec<(TaskManagerTarget, Vec<String>)>,
+    pub task_manager_history: Vec<crate::sysmon::Sample>,
+    pub task_manager_sort: crate::sysmon::SortKey,
+    pub task_manager_sort_ascending: bool,
     pub task_manager_status: String,
+
+    // Boot entries state: the firmware's variables and the staged changes
+    pub boot_entries_list: ListView,
+    /// Boot#### id of each list row.
+    pub boot_entries_ids: Vec<u16>,
+    pub boot_entries_state: crate::bootvar::State,
+    pub boot_entries_plan: crate::bootvar::Plan,
+    pub boot_entries_status: String,
+
+    // About this PC state
+    pub about_pc_lines: Vec<String>,
+    pub about_pc_scroll: usize,
+
+    // Mail state
+    pub mail_view: MailView,
+    pub mail_entries: Vec<crate::net::mail::MailSummary>,
+    pub mail_selected: Option<usize>,
+    pub mail_list_scroll: usize,
+    pub mail_body_lines: Vec<String>,
+    pub mail_body_scroll: usize,
+    /// Compose: to, subject, body. Account: email, password, IMAP, SMTP.
+    pub mail_fields: Vec<String>,
+    pub mail_focus: usize,
+    pub mail_status: String,
+
+    /// Gamepad events received while focused, oldest first, for apps that
+    /// read the controller directly.
+    pub gamepad_events: alloc::collections::VecDeque<crate::gamepad::GamepadEvent>,
+    pub drag_events: alloc::collections::VecDeque<DragEvent>,
 }
 
 impl Window {
@@ -549,6 +762,7 @@ impl Window {
         Self {
             id,
             desktop_id: 1,
+            opacity: 100,
             rect: Rect::new(x, y, width, height),
             title: String::from(title),
             buffer: alloc::vec![0xFFFFFFFF; buffer_size],
@@ -561,7 +775,7 @@ impl Window {
             output_lines: alloc::vec![],
             terminal_scroll: 0,
             cursor_x: 0,
-            current_dir_cluster: unsafe { crate::fat32::GLOBAL_FAT.root_cluster },
+            current_dir_cluster: unsafe { crate::fat32::GLOBAL_FAT.get().root_cluster },
             current_path: String::from("REDUX/"),
 
             explorer_items: alloc::vec![],
@@ -580,9 +794,9 @@ impl Window {
             explorer_side_panel_dir_size: None,
 
             notepad_file_name: String::from("NOTE.TXT"),
-            notepad_text: String::new(),
+            notepad_area: TextArea::default(),
             notepad_status: String::from("Ready."),
-            notepad_dir_cluster: unsafe { crate::fat32::GLOBAL_FAT.root_cluster },
+            notepad_dir_cluster: unsafe { crate::fat32::GLOBAL_FAT.get().root_cluster },
             notepad_dir_path: String::from("/"),
             notepad_edit_name: false,
 
@@ -605,6 +819,32 @@ impl Window {
             image_viewer_width: 0,
             image_viewer_height: 0,
             image_viewer_pixels: alloc::vec![],
+            pdf_viewer_file_name: String::new(),
+            pdf_viewer_status: String::new(),
+            pdf_viewer_document: None,
+            pdf_viewer_page: 0,
+            pdf_viewer_zoom: 100,
+            pdf_viewer_fit_width: true,
+            pdf_viewer_rendered: None,
+            pdf_viewer_scroll_x: 0,
+            pdf_viewer_scroll_y: 0,
+
+            calculator: crate::calc::Calculator::new(),
+            calculator_history: ListView::default(),
+            calendar_selected: crate::calendar::Date {
+                year: 1970,
+                month: 1,
+                day: 1,
+            },
+            calendar_events: ListView::default(),
+            calendar_input: String::new(),
+            calendar_status: String::new(),
+            clock_list: ListView::default(),
+            clock_status: String::new(),
+            clock_shown_second: -1,
+            recorder_status: String::new(),
+            recorder_shown: (u64::MAX, 0),
+            settings_shown_level: None,
 
             app_runner_source_file: String::new(),
             app_runner_rml_source: String::new(),
@@ -938,10 +1178,156 @@ Tab DOCS is read-only.\n"
             wifi_status_msg: String::new(),
             wifi_mode_active: false,
 
-            task_manager_lines: Vec::new(),
-            task_manager_scroll: 0,
-            task_manager_selected: None,
+            task_manager_list: ListView::default(),
+            task_manager_targets: Vec::new(),
+            task_manager_processes: Vec::new(),
+            task_manager_jobs: Vec::new(),
+            task_manager_history: Vec::new(),
+            task_manager_sort: crate::sysmon::SortKey::Cpu,
+            task_manager_sort_ascending: false,
             task_manager_status: String::new(),
+
+            boot_entries_list: ListView::default(),
+            boot_entries_ids: Vec::new(),
+            boot_entries_state: crate::bootvar::State::default(),
+            boot_entries_plan: crate::bootvar::Plan::default(),
+            boot_entries_status: String::new(),
+
+            about_pc_lines: Vec::new(),
+            about_pc_scroll: 0,
+
+            mail_view: MailView::Inbox,
+            mail_entries: Vec::new(),
+            mail_selected: None,
+            mail_list_scroll: 0,
+            mail_body_lines: Vec::new(),
+            mail_body_scroll: 0,
+            mail_fields: Vec::new(),
+            mail_focus: 0,
+            mail_status: String::new(),
+
+            gamepad_events: alloc::collections::VecDeque::new(),
+            drag_events: alloc::collections::VecDeque::new(),
+        }
+    }
+
+    pub fn push_gamepad_event(&mut self, event: crate::gamepad::GamepadEvent) {
+        const GAMEPAD_QUEUE_LIMIT: usize = 64;
+        if self.gamepad_events.len() >= GAMEPAD_QUEUE_LIMIT {
+            self.gamepad_events.pop_front();
+        }
+        self.gamepad_events.push_back(event);
+    }
+
+    pub fn take_gamepad_events(&mut self) -> Vec<crate::gamepad::GamepadEvent> {
+        self.gamepad_events.drain(..).collect()
+    }
+
+    pub fn push_drag_event(&mut self, event: DragEvent) {
+        const DRAG_QUEUE_LIMIT: usize = 64;
+        if self.drag_events.len() >= DRAG_QUEUE_LIMIT {
+            self.drag_events.pop_front();
+        }
+        self.drag_events.push_back(event);
+    }
+
+    pub fn take_drag_events(&mut self) -> Vec<DragEvent> {
+        self.drag_events.drain(..).collect()
+    }
+
+    /// Payload kinds this window takes on drop. Explorer drops are handled by
+    /// the compositor (copy into the open folder); the rest here.
+    pub fn accepts_drop(&self, kind: PayloadKind) -> bool {
+        match self.kind {
+            WindowKind::Terminal | WindowKind::Notepad => true,
+            WindowKind::Explorer => kind == PayloadKind::Files,
+            _ => false,
+        }
+    }
+
+    /// Insert dropped or pasted text at the input point.
+    pub fn insert_text(&mut self, text: &str) {
+        match self.kind {
+            WindowKind::Terminal => {
+                for ch in text.chars() {
+                    if ch.is_ascii() && !ch.is_control() {
+                        self.input_buffer.push(ch);
+                    } else if ch == '\n' || ch == '\t' {
+                        self.input_buffer.push(' ');
+                    }
+                }
+                self.render();
+            }
+            WindowKind::Notepad if !self.notepad_edit_name => {
+                let ascii: String = text.chars().filter(|ch| ch.is_ascii()).collect();
+                self.notepad_area_mut().insert_str(ascii.as_str());
+                self.render();
+            }
+            _ => {}
+        }
+    }
+
+    /// Right-click menu for windows without a menu of their own.
+    pub fn context_menu_items(&self) -> Vec<MenuItem> {
+        let can_paste = crate::gui::clipboard::get().is_some();
+        let paste = if can_paste {
+            MenuItem::new("Pegar", CONTEXT_PASTE)
+        } else {
+            MenuItem::disabled("Pegar", CONTEXT_PASTE)
+        };
+        match self.kind {
+            WindowKind::Terminal => alloc::vec![
+                MenuItem::new("Copiar ultima linea", CONTEXT_COPY_LAST_LINE),
+                paste,
+                MenuItem::new("Limpiar", CONTEXT_CLEAR),
+            ],
+            WindowKind::Notepad => alloc::vec![
+                if self.notepad_area.selection().is_some() {
+                    MenuItem::new("Copiar", CONTEXT_COPY_ALL)
+                } else {
+                    MenuItem::new("Copiar todo", CONTEXT_COPY_ALL)
+                },
+                MenuItem::new("Seleccionar todo", CONTEXT_SELECT_ALL),
+                paste,
+                MenuItem::new("Borrar texto", CONTEXT_CLEAR),
+            ],
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn apply_context_action(&mut self, action: u32) {
+        use crate::gui::clipboard::{self, Payload};
+        match (self.kind, action) {
+            (_, CONTEXT_PASTE) => {
+                if let Some(payload) = clipboard::get() {
+                    self.insert_text(payload.as_text().as_str());
+                }
+            }
+            (WindowKind::Terminal, CONTEXT_COPY_LAST_LINE) => {
+                if let Some(line) = self.output_lines.iter().rev().find(|l| !l.trim().is_empty()) {
+                    clipboard::set(Payload::Text(line.clone()));
+                }
+            }
+            (WindowKind::Terminal, CONTEXT_CLEAR) => {
+                self.output_lines.clear();
+                self.terminal_scroll = 0;
+                self.render();
+            }
+            (WindowKind::Notepad, CONTEXT_COPY_ALL) => {
+                let text = self.notepad_area.selected_text().unwrap_or(self.notepad_area.text());
+                clipboard::set(Payload::Text(String::from(text)));
+                self.set_notepad_status("Texto copiado al portapapeles.");
+            }
+            (WindowKind::Notepad, CONTEXT_SELECT_ALL) => {
+                self.notepad_edit_name = false;
+                self.notepad_area_mut().select_all();
+                self.render();
+            }
+            (WindowKind::Notepad, CONTEXT_CLEAR) => {
+                self.notepad_area.clear();
+                self.render();
+            }
+            _ => {}
         }
     }
 
@@ -1060,6 +1446,63 @@ Tab DOCS is read-only.\n"
         win
     }
 
+    pub fn new_pdf_viewer(id: usize, title: &str, x: i32, y: i32, width: u32, height: u32) -> Self {
+        let mut win = Self::new_base(id, title, x, y, width, height);
+        win.kind = WindowKind::PdfViewer;
+        win.pdf_viewer_status = String::from("Abre un PDF desde el Explorador o con 'pdf open <archivo>'.");
+        win.render();
+        win
+    }
+
+    pub fn new_calculator(id: usize, title: &str, x: i32, y: i32, width: u32, height: u32) -> Self {
+        let mut win = Self::new_base(id, title, x, y, width, height);
+        win.kind = WindowKind::Calculator;
+        win.calculator_history = ListView::new(win.calculator_history_rect(), Vec::new());
+        win.render();
+        win
+    }
+
+    pub fn new_calendar(id: usize, title: &str, x: i32, y: i32, width: u32, height: u32) -> Self {
+        let mut win = Self::new_base(id, title, x, y, width, height);
+        win.kind = WindowKind::Calendar;
+        win.calendar_selected = crate::calendar::today();
+        win.calendar_events = ListView::new(
+            win.calendar_events_rect(),
+            alloc::vec![Column::new("Hora", 80), Column::new("Evento", 0)],
+        );
+        win.calendar_reload();
+        win.render();
+        win
+    }
+
+    pub fn new_clock(id: usize, title: &str, x: i32, y: i32, width: u32, height: u32) -> Self {
+        let mut win = Self::new_base(id, title, x, y, width, height);
+        win.kind = WindowKind::Clock;
+        win.clock_list = ListView::new(
+            win.clock_list_rect(),
+            alloc::vec![
+                Column::new("Hora", 64),
+                Column::new("Tipo", 84),
+                Column::new("Estado", 64),
+                Column::new("Texto", 0),
+            ],
+        );
+        win.rebuild_clock_list();
+        win.render();
+        win
+    }
+
+    pub fn new_recorder(id: usize, title: &str, x: i32, y: i32, width: u32, height: u32) -> Self {
+        let mut win = Self::new_base(id, title, x, y, width, height);
+        win.kind = WindowKind::Recorder;
+        win.recorder_status = match crate::capture::last_saved() {
+            Some(path) => alloc::format!("Ultima grabacion: {}", path),
+            None => String::from("Pulsa Grabar o la barra espaciadora."),
+        };
+        win.render();
+        win
+    }
+
     pub fn new_settings(id: usize, title: &str, x: i32, y: i32, width: u32, height: u32) -> Self {
         let mut win = Self::new_base(id, title, x, y, width, height);
         win.kind = WindowKind::Settings;
@@ -1070,7 +1513,7 @@ Tab DOCS is read-only.\n"
     pub fn new_wifi_manager(id: usize, title: &str, x: i32, y: i32, width: u32, height: u32) -> Self {
         let mut win = Self::new_base(id, title, x, y, width, height);
         win.kind = WindowKind::WifiManager;
-        win.wifi_mode_active = unsafe { crate::net::FAILOVER_POLICY == crate::net::FAILOVER_WIFI_FIRST };
+        win.wifi_mode_active = crate::net::get_failover_policy() == crate::net::FAILOVER_WIFI_FIRST;
         win.wifi_status_msg = String::from(crate::intel_wifi::get_status());
         win.render();
         win
@@ -1080,6 +1523,54 @@ Tab DOCS is read-only.\n"
         let mut win = Self::new_base(id, title, x, y, width, height);
         win.kind = WindowKind::TaskManager;
         win.task_manager_status = String::from("Listo.");
+        win.task_manager_list = ListView::new(
+            win.task_manager_list_rect(),
+            alloc::vec![
+                Column::new("PID", 40),
+                Column::new("Nombre", 0),
+                Column::new("Anillo", 48),
+                Column::new("Estado", 84),
+                Column::new("Hilos", 40),
+                Column::new("CPU%", 48),
+                Column::new("Memoria", 72),
+                Column::new("Red", 72),
+            ],
+        );
+        win.render();
+        win
+    }
+
+    pub fn new_about_pc(id: usize, title: &str, x: i32, y: i32, width: u32, height: u32) -> Self {
+        let mut win = Self::new_base(id, title, x, y, width, height);
+        win.kind = WindowKind::AboutPc;
+        win.about_pc_lines = crate::hwinfo::report_lines();
+        win.render();
+        win
+    }
+
+    pub fn new_boot_entries(id: usize, title: &str, x: i32, y: i32, width: u32, height: u32) -> Self {
+        let mut win = Self::new_base(id, title, x, y, width, height);
+        win.kind = WindowKind::BootEntries;
+        win.boot_entries_list = ListView::new(
+            win.boot_entries_list_rect(),
+            alloc::vec![
+                Column::new("#", 28),
+                Column::new("ID", 72),
+                Column::new("Estado", 120),
+                Column::new("Nombre", 0),
+                Column::new("Ruta", 200),
+            ],
+        );
+        win.reload_boot_entries();
+        win.render();
+        win
+    }
+
+    pub fn new_mail(id: usize, title: &str, x: i32, y: i32, width: u32, height: u32) -> Self {
+        let mut win = Self::new_base(id, title, x, y, width, height);
+        win.kind = WindowKind::Mail;
+        win.mail_entries = crate::net::mail::cached_inbox();
+        win.mail_status = String::from("Listo. Pulsa Actualizar para leer la bandeja.");
         win.render();
         win
     }
@@ -1356,7 +1847,8 @@ Tab DOCS is read-only.\n"
     }
 
     fn notepad_filename_rect(&self) -> Rect {
-        let raw_x = (self.rect.width as i32 - 360).max(10);
+        // Never under the button row (five buttons end at x = 374).
+        let raw_x = (self.rect.width as i32 - 360).max(10 + 5 * 74);
         let x = raw_x.min((self.rect.width as i32 - 90).max(10));
         let width = (self.rect.width as i32 - x - 10).max(80) as u32;
         Rect::new(x, 7, width, 20)
@@ -1368,6 +1860,12 @@ Tab DOCS is read-only.\n"
         Rect::new(8, y, self.rect.width.saturating_sub(16), h)
     }
 
+    /// The text area with its rect matching the current window size.
+    fn notepad_area_mut(&mut self) -> &mut TextArea {
+        self.notepad_area.rect = self.notepad_editor_rect();
+        &mut self.notepad_area
+    }
+
     fn notepad_status_rect(&self) -> Rect {
         let y = (self.content_height() - NOTEPAD_STATUS_H).max(0);
         Rect::new(0, y, self.rect.width, NOTEPAD_STATUS_H as u32)
@@ -1415,7 +1913,7 @@ Tab DOCS is read-only.\n"
 
     fn browser_url_rect(&self) -> Rect {
         let x = 70; // Back/Fwd buttons space
-        let width = self.rect.width.saturating_sub(x as u32 + 140); // Go + scroll controls
+        let width = self.rect.width.saturating_sub(x as u32 + 260); // Go + scroll + download + print controls
         Rect::new(x, 10, width, 24)
     }
 
@@ -1436,6 +1934,16 @@ Tab DOCS is read-only.\n"
         Rect::new(up.x, up.y + 13, up.width, 11)
     }
 
+    fn browser_download_rect(&self) -> Rect {
+        let up = self.browser_scroll_up_rect();
+        Rect::new(up.x + up.width as i32 + 8, 10, 52, 24)
+    }
+
+    fn browser_print_rect(&self) -> Rect {
+        let download = self.browser_download_rect();
+        Rect::new(download.x + download.width as i32 + 8, 10, 52, 24)
+    }
+
     fn browser_viewport_rect(&self) -> Rect {
         let y = BROWSER_TOP_H;
         let h = (self.content_height() - y - BROWSER_STATUS_H).max(0) as u32;
@@ -1448,6 +1956,12 @@ Tab DOCS is read-only.\n"
         Rect::new(0, y, self.rect.width, h)
     }
 
+    fn pdf_viewer_canvas_rect(&self) -> Rect {
+        let y = PDF_VIEWER_TOOLBAR_H;
+        let h = (self.content_height() - y - PDF_VIEWER_STATUS_H).max(0) as u32;
+        Rect::new(0, y, self.rect.width, h)
+    }
+
     fn app_runner_canvas_rect(&self) -> Rect {
         let y = APP_RUNNER_TOP_H;
         let h = (self.content_height() - y - APP_RUNNER_STATUS_H).max(0) as u32;
@@ -3183,7 +3697,12 @@ Tab DOCS is read-only.\n"
             .map(|b| (b as char).to_ascii_lowercase())
             .collect::<String>();
 
-        if lower.starts_with("http://") || lower.starts_with("https://") || lower.starts_with("redux://") {
+        if lower.starts_with("http://")
+            || lower.starts_with("https://")
+            || lower.starts_with("redux://")
+            || lower.starts_with("gemini://")
+            || lower.starts_with("gopher://")
+        {
             return Some(String::from(clean));
         }
         None
@@ -3563,6 +4082,47 @@ Tab DOCS is read-only.\n"
         self.kind == WindowKind::TaskManager
     }
 
+    pub fn is_about_pc(&self) -> bool {
+        self.kind == WindowKind::AboutPc
+    }
+
+    pub fn is_mail(&self) -> bool {
+        self.kind == WindowKind::Mail
+    }
+
+    pub fn is_boot_entries(&self) -> bool {
+        self.kind == WindowKind::BootEntries
+    }
+
+    pub fn is_pdf_viewer(&self) -> bool {
+        self.kind == WindowKind::PdfViewer
+    }
+
+    pub fn is_calculator(&self) -> bool {
+        self.kind == WindowKind::Calculator
+    }
+
+    pub fn is_calendar(&self) -> bool {
+        self.kind == WindowKind::Calendar
+    }
+
+    pub fn is_clock(&self) -> bool {
+        self.kind == WindowKind::Clock
+    }
+
+    pub fn is_recorder(&self) -> bool {
+        self.kind == WindowKind::Recorder
+    }
+
+    /// Calculator, calendar, clock or voice recorder: the applets that take
+    /// their keys through `applet_key`.
+    pub fn is_applet(&self) -> bool {
+        matches!(
+            self.kind,
+            WindowKind::Calculator | WindowKind::Calendar | WindowKind::Clock | WindowKind::Recorder
+        )
+    }
+
     pub fn title_bar_contains(&self, x: i32, y: i32) -> bool {
         let bar = Rect::new(self.rect.x, self.rect.y, self.rect.width, TITLE_BAR_H as u32);
         bar.contains(crate::gui::Point { x, y })
@@ -3592,6 +4152,14 @@ Tab DOCS is read-only.\n"
             WindowKind::VideoPlayer => (640, 480),
             WindowKind::WifiManager => (420, 460),
             WindowKind::TaskManager => (520, 360),
+            WindowKind::AboutPc => (480, 320),
+            WindowKind::Mail => (600, 380),
+            WindowKind::BootEntries => (560, 400),
+            WindowKind::PdfViewer => (520, 380),
+            WindowKind::Calculator => (260, 460),
+            WindowKind::Calendar => (360, 460),
+            WindowKind::Clock => (360, 340),
+            WindowKind::Recorder => (320, 220),
         }
     }
 
@@ -3645,6 +4213,14 @@ Tab DOCS is read-only.\n"
             WindowKind::VideoPlayer => self.render_video_player(),
             WindowKind::WifiManager => self.render_wifi_manager(),
             WindowKind::TaskManager => self.render_task_manager(),
+            WindowKind::AboutPc => self.render_about_pc(),
+            WindowKind::Mail => self.render_mail(),
+            WindowKind::BootEntries => self.render_boot_entries(),
+            WindowKind::PdfViewer => self.render_pdf_viewer(),
+            WindowKind::Calculator => self.render_calculator(),
+            WindowKind::Calendar => self.render_calendar(),
+            WindowKind::Clock => self.render_clock(),
+            WindowKind::Recorder => self.render_recorder(),
         }
     }
 
@@ -3692,7 +4268,9 @@ Tab DOCS is read-only.\n"
         );
 
         let cursor_x = TERMINAL_TEXT_X + total_prompt_w + (input_clone.len() * TERMINAL_CHAR_W);
-        self.draw_text(cursor_x as u32, y as u32, b"_", Color(0x000000));
+        if crate::gui::caret::visible() {
+            self.draw_text(cursor_x as u32, y as u32, b"_", Color(0x000000));
+        }
         self.cursor_x = cursor_x;
     }
 
@@ -3828,48 +4406,32 @@ Tab DOCS is read-only.\n"
                         self.draw_text(panel_x as u32 + 8, py as u32, alloc::format!("Tipo: {}", ext).as_bytes(), Color(0x394C5D));
                         py += 16;
 
-                        let mb = item.size / (1024 * 1024);
-                        let mb_frac = (item.size % (1024 * 1024)) * 100 / (1024 * 1024);
-                        self.draw_text(panel_x as u32 + 8, py as u32, alloc::format!("Peso: {}.{:02} MB", mb, mb_frac).as_bytes(), Color(0x394C5D));
+                        let size = crate::i18n::format::size(item.size as u64);
+                        self.draw_text(panel_x as u32 + 8, py as u32, alloc::format!("Peso: {}", size).as_bytes(), Color(0x394C5D));
                         py += 16;
                     } else if item.kind == ExplorerItemKind::Directory {
                         self.draw_text(panel_x as u32 + 8, py as u32, b"Tipo: Carpeta", Color(0x394C5D));
                         py += 16;
 
                         if let Some(size) = self.explorer_side_panel_dir_size {
-                            let mb = size / (1024 * 1024);
-                            let mb_frac = (size % (1024 * 1024)) * 100 / (1024 * 1024);
-                            self.draw_text(panel_x as u32 + 8, py as u32, alloc::format!("Peso: {}.{:02} MB", mb, mb_frac).as_bytes(), Color(0x394C5D));
+                            let size = crate::i18n::format::size(size);
+                            self.draw_text(panel_x as u32 + 8, py as u32, alloc::format!("Peso: {}", size).as_bytes(), Color(0x394C5D));
                         } else {
                             self.draw_text(panel_x as u32 + 8, py as u32, b"Peso: ---", Color(0x394C5D));
                         }
                         py += 16;
                     }
 
-                    let c_year = (item.create_date >> 9) + 1980;
-                    let c_month = (item.create_date >> 5) & 0x0F;
-                    let c_day = item.create_date & 0x1F;
-                    let c_hour = item.create_time >> 11;
-                    let c_min = (item.create_time >> 5) & 0x3F;
-
-                    let w_year = (item.write_date >> 9) + 1980;
-                    let w_month = (item.write_date >> 5) & 0x0F;
-                    let w_day = item.write_date & 0x1F;
-                    let w_hour = item.write_time >> 11;
-                    let w_min = (item.write_time >> 5) & 0x3F;
-
-                    let c_str = if item.create_date == 0 {
-                        String::from("Creacion: N/A")
-                    } else {
-                        alloc::format!("Creado: {:02}/{:02}/{} {:02}:{:02}", c_day, c_month, c_year, c_hour, c_min)
+                    let c_str = match crate::i18n::format::fat_date_time(item.create_date, item.create_time) {
+                        Some(text) => alloc::format!("Creado: {}", text),
+                        None => String::from("Creacion: N/A"),
                     };
                     self.draw_text(panel_x as u32 + 8, py as u32, c_str.as_bytes(), Color(0x394C5D));
                     py += 16;
 
-                    let w_str = if item.write_date == 0 {
-                        String::from("Modif.: N/A")
-                    } else {
-                        alloc::format!("Modif.: {:02}/{:02}/{} {:02}:{:02}", w_day, w_month, w_year, w_hour, w_min)
+                    let w_str = match crate::i18n::format::fat_date_time(item.write_date, item.write_time) {
+                        Some(text) => alloc::format!("Modif.: {}", text),
+                        None => String::from("Modif.: N/A"),
                     };
                     self.draw_text(panel_x as u32 + 8, py as u32, w_str.as_bytes(), Color(0x394C5D));
                 }
@@ -3909,9 +4471,9 @@ Tab DOCS is read-only.\n"
         self.fill_rect(Rect::new(0, 0, self.rect.width, NOTEPAD_TOP_H as u32), Color(0xD7E6F8));
         self.fill_rect(Rect::new(0, NOTEPAD_TOP_H, self.rect.width, 1), Color(0xA6BED6));
 
-        let button_labels = ["NEW", "SAVE", "DELETE"];
-        let button_colors = [0x4A8BC2, 0x3CA66B, 0xC45A57];
-        for i in 0..3 {
+        let button_labels = ["NEW", "OPEN", "SAVE", "DELETE", "PRINT"];
+        let button_colors = [0x4A8BC2, 0x5A7FB0, 0x3CA66B, 0xC45A57, 0x7A6BB0];
+        for i in 0..button_labels.len() {
             let rect = self.notepad_button_rect(i);
             self.fill_rect(rect, Color(button_colors[i]));
             self.draw_border(rect, Color(0x23374D));
@@ -3944,33 +4506,17 @@ Tab DOCS is read-only.\n"
         );
 
         let editor = self.notepad_editor_rect();
-        self.fill_rect(editor, Color(0xFFFFFF));
-        self.draw_border(editor, Color(0xAFC0D3));
-
-        let max_cols = ((editor.width as i32 - 8) / 6).max(1) as usize;
-        let max_lines = ((editor.height as i32 - 8) / 9).max(1) as usize;
-        let lines = Self::wrap_text_lines(self.notepad_text.as_str(), max_cols, max_lines);
-
-        for (i, line) in lines.iter().enumerate() {
-            self.draw_text(
-                (editor.x + 4) as u32,
-                (editor.y + 4 + (i as i32 * 9)) as u32,
-                line.as_bytes(),
-                Color(0x182736),
-            );
-        }
+        let mut area = core::mem::take(&mut self.notepad_area);
+        area.rect = editor;
+        area.focused = !self.notepad_edit_name;
+        area.draw(self, editor);
+        self.notepad_area = area;
 
         if self.notepad_edit_name {
             let caret_x = (name_rect.x + 4 + file_text_trim.len() as i32 * 6)
                 .min(name_rect.x + name_rect.width as i32 - 8)
                 .max(name_rect.x + 4);
             self.draw_text(caret_x as u32, (name_rect.y + 7) as u32, b"_", Color(0x1E3C5A));
-        } else {
-            let line_idx = lines.len().saturating_sub(1);
-            let col = lines.get(line_idx).map(|s| s.len()).unwrap_or(0);
-            let caret_x = (editor.x + 4 + col as i32 * 6).min(editor.x + editor.width as i32 - 8);
-            let caret_y = (editor.y + 4 + line_idx as i32 * 9).min(editor.y + editor.height as i32 - 10);
-            self.draw_text(caret_x as u32, caret_y as u32, b"_", Color(0x182736));
         }
 
         let status_rect = self.notepad_status_rect();
@@ -4138,30 +4684,27 @@ Tab DOCS is read-only.\n"
         self.draw_text((up_rect.x + 7) as u32, (up_rect.y + 2) as u32, b"^", Color(0x1E2E40));
         self.draw_text((down_rect.x + 7) as u32, (down_rect.y + 2) as u32, b"v", Color(0x1E2E40));
 
+        // Download Button
+        let download_rect = self.browser_download_rect();
+        self.fill_rect(download_rect, Color(0x3CA66B));
+        self.draw_border(download_rect, Color(0x2E8655));
+        self.draw_text((download_rect.x + 8) as u32, (download_rect.y + 8) as u32, b"BAJAR", Color(0xFFFFFF));
+
+        // Print Button
+        let print_rect = self.browser_print_rect();
+        self.fill_rect(print_rect, Color(0x7A6BB0));
+        self.draw_border(print_rect, Color(0x5E5190));
+        self.draw_text((print_rect.x + 14) as u32, (print_rect.y + 8) as u32, b"IMPR", Color(0xFFFFFF));
+
         // Viewport
         let view_rect = self.browser_viewport_rect();
         self.fill_rect(view_rect, Color(0xFFFFFF));
         let surf_w = self.browser_surface_width as usize;
         let surf_h = self.browser_surface_height as usize;
-        let has_surface = surf_w > 0
-            && surf_h > 0
-            && self.browser_surface_pixels.len() >= surf_w.saturating_mul(surf_h);
-
-        if has_surface {
-            let avail_w = (view_rect.width as i32 - 8).max(1) as usize;
-            let avail_h = (view_rect.height as i32 - 8).max(1) as usize;
-
-            let mut draw_w = avail_w;
-            let mut draw_h = (surf_h.saturating_mul(draw_w)).max(1) / surf_w.max(1);
-            if draw_h > avail_h {
-                draw_h = avail_h;
-                draw_w = (surf_w.saturating_mul(draw_h)).max(1) / surf_h.max(1);
-            }
-            draw_w = draw_w.max(1).min(avail_w);
-            draw_h = draw_h.max(1).min(avail_h);
 
-            let start_x = view_rect.x + ((view_rect.width as i32 - draw_w as i32) / 2);
-            let start_y = view_rect.y + ((view_rect.height as i32 - draw_h as i32) / 2);
+        if let Some(draw) = self.browser_surface_draw_rect() {
+            let (start_x, start_y) = (draw.x, draw.y);
+            let (draw_w, draw_h) = (draw.width as usize, draw.height as usize);
 
             for dy in 0..draw_h {
                 let sy = dy.saturating_mul(surf_h) / draw_h.max(1);
@@ -4347,62 +4890,996 @@ Tab DOCS is read-only.\n"
         self.draw_text(8, (status_y + 10) as u32, status_trim.as_bytes(), Color(0x2B4258));
     }
 
-    pub fn render_settings(&mut self) {
-        if self.kind != WindowKind::Settings {
+    fn pdf_viewer_buttons(&self) -> [(Rect, PdfViewerClickAction, &'static str); 5] {
+        let button = |i: i32, w: u32| Rect::new(10 + i * 44, 6, w, 24);
+        [
+            (button(0, 36), PdfViewerClickAction::Prev, "<"),
+            (button(1, 36), PdfViewerClickAction::Next, ">"),
+            (button(2, 36), PdfViewerClickAction::ZoomOut, "-"),
+            (button(3, 36), PdfViewerClickAction::ZoomIn, "+"),
+            (button(4, 64), PdfViewerClickAction::Fit, "Ajustar"),
+        ]
+    }
+
+    /// Zoom that makes the current page as wide as the viewport.
+    fn pdf_viewer_fit_zoom(&self) -> u32 {
+        let Some((width_pt, _)) = self
+            .pdf_viewer_document
+            .as_ref()
+            .and_then(|doc| doc.page_size_pt(self.pdf_viewer_page))
+        else {
+            return 100;
+        };
+        let avail = (self.pdf_viewer_canvas_rect().width as i32 - 2 * PDF_VIEWER_MARGIN).max(1) as f32;
+        ((avail * 100.0 / width_pt.max(1.0)) as u32).clamp(crate::pdf::MIN_ZOOM, crate::pdf::MAX_ZOOM)
+    }
+
+    /// Render the current page at the current zoom into `pdf_viewer_rendered`.
+    fn pdf_viewer_render_page(&mut self) {
+        if self.pdf_viewer_fit_width {
+            self.pdf_viewer_zoom = self.pdf_viewer_fit_zoom();
+        }
+        let Some(doc) = self.pdf_viewer_document.as_ref() else {
             return;
+        };
+        let count = doc.page_count();
+        match doc.render_page(self.pdf_viewer_page, self.pdf_viewer_zoom) {
+            Ok(page) => {
+                self.pdf_viewer_rendered = Some(page);
+                self.pdf_viewer_status = alloc::format!(
+                    "Pagina {}/{}  -  zoom {}%{}",
+                    self.pdf_viewer_page + 1,
+                    count,
+                    self.pdf_viewer_zoom,
+                    if self.pdf_viewer_fit_width { " (ajustado)" } else { "" }
+                );
+            }
+            Err(err) => {
+                self.pdf_viewer_rendered = None;
+                self.pdf_viewer_status = alloc::format!("Pagina {}: {}", self.pdf_viewer_page + 1, err);
+            }
+        }
+        self.pdf_viewer_clamp_scroll();
+    }
+
+    /// Scroll limits for the rendered page: (max x, max y).
+    fn pdf_viewer_scroll_limits(&self) -> (i32, i32) {
+        let view = self.pdf_viewer_canvas_rect();
+        match self.pdf_viewer_rendered.as_ref() {
+            Some(page) => (
+                (page.width as i32 + 2 * PDF_VIEWER_MARGIN - view.width as i32).max(0),
+                (page.height as i32 + 2 * PDF_VIEWER_MARGIN - view.height as i32).max(0),
+            ),
+            None => (0, 0),
         }
+    }
+
+    fn pdf_viewer_clamp_scroll(&mut self) {
+        let (max_x, max_y) = self.pdf_viewer_scroll_limits();
+        self.pdf_viewer_scroll_x = self.pdf_viewer_scroll_x.clamp(0, max_x);
+        self.pdf_viewer_scroll_y = self.pdf_viewer_scroll_y.clamp(0, max_y);
+    }
 
+    pub fn render_pdf_viewer(&mut self) {
+        if self.kind != WindowKind::PdfViewer {
+            return;
+        }
         let content_h = self.content_height();
         if content_h <= 0 {
             return;
         }
+        // Resizing changes the fit-to-width zoom.
+        if self.pdf_viewer_fit_width
+            && self.pdf_viewer_document.is_some()
+            && self.pdf_viewer_fit_zoom() != self.pdf_viewer_zoom
+        {
+            self.pdf_viewer_render_page();
+        }
+
+        self.fill_rect(Rect::new(0, 0, self.rect.width, PDF_VIEWER_TOOLBAR_H as u32), Color(0xE5E7EB));
+        self.fill_rect(Rect::new(0, PDF_VIEWER_TOOLBAR_H - 1, self.rect.width, 1), Color(0x9CA3AF));
+        for (rect, _, label) in self.pdf_viewer_buttons().iter() {
+            self.fill_rect(*rect, Color(0xF9FAFB));
+            self.draw_border(*rect, Color(0x6B7280));
+            let text_x = rect.x + (rect.width as i32 - label.len() as i32 * 6) / 2;
+            self.draw_text(text_x.max(0) as u32, (rect.y + 8) as u32, label.as_bytes(), Color(0x111827));
+        }
+        let name_x = 10 + 4 * 44 + 76;
+        let max_chars = ((self.rect.width as i32 - name_x - 10) / 6).max(4) as usize;
+        let name = Self::trim_label(self.pdf_viewer_file_name.as_str(), max_chars);
+        self.draw_text(name_x as u32, 14, name.as_bytes(), Color(0x1F2937));
+
+        let view = self.pdf_viewer_canvas_rect();
+        self.fill_rect(view, Color(0x525659));
+        match self.pdf_viewer_rendered.as_ref() {
+            Some(page) => {
+                let (page_w, page_h) = (page.width as i32, page.height as i32);
+                let origin_x = if page_w + 2 * PDF_VIEWER_MARGIN <= view.width as i32 {
+                    view.x + (view.width as i32 - page_w) / 2
+                } else {
+                    view.x + PDF_VIEWER_MARGIN - self.pdf_viewer_scroll_x
+                };
+                let origin_y = view.y + PDF_VIEWER_MARGIN - self.pdf_viewer_scroll_y;
+                let x0 = origin_x.max(view.x).max(0);
+                let x1 = (origin_x + page_w).min(view.x + view.width as i32).min(self.rect.width as i32);
+                let y0 = origin_y.max(view.y);
+                let y1 = (origin_y + page_h).min(view.y + view.height as i32);
+                if x0 < x1 {
+                    let stride = self.rect.width as usize;
+                    for y in y0.max(0)..y1 {
+                        let src = (y - origin_y) as usize * page.width as usize + (x0 - origin_x) as usize;
+                        let dst = y as usize * stride + x0 as usize;
+                        let len = (x1 - x0) as usize;
+                        if let (Some(from), Some(to)) =
+                            (page.pixels.get(src..src + len), self.buffer.get_mut(dst..dst + len))
+                        {
+                            to.copy_from_slice(from);
+                        }
+                    }
+                }
+            }
+            None => {
+                let message = if self.pdf_viewer_document.is_some() {
+                    "No se pudo dibujar esta pagina."
+                } else {
+                    "Ningun documento abierto."
+                };
+                self.draw_text((view.x + 14) as u32, (view.y + 16) as u32, message.as_bytes(), Color(0xF3F4F6));
+            }
+        }
 
-        // Background
-        self.fill_rect(Rect::new(0, 0, self.rect.width, content_h as u32), Color(0xF2F2F2));
-
-        // Header
-        self.fill_rect(Rect::new(0, 0, self.rect.width, 40), Color(0x34495E));
-        self.draw_text(15, 15, b"Configuracion del Sistema", Color(0xFFFFFF));
+        let status_y = (content_h - PDF_VIEWER_STATUS_H).max(0);
+        self.fill_rect(Rect::new(0, status_y, self.rect.width, PDF_VIEWER_STATUS_H as u32), Color(0xE5E7EB));
+        self.fill_rect(Rect::new(0, status_y, self.rect.width, 1), Color(0x9CA3AF));
+        let max_chars = ((self.rect.width as i32 - 16) / 6).max(4) as usize;
+        let status = Self::trim_label(self.pdf_viewer_status.as_str(), max_chars);
+        self.draw_text(8, (status_y + 8) as u32, status.as_bytes(), Color(0x374151));
+    }
 
-        let mut y = 60;
+    fn calculator_key_w(&self) -> i32 {
+        let columns = crate::calc::KEYPAD_COLUMNS as i32;
+        ((self.rect.width as i32 - 20 - (columns - 1) * CALCULATOR_KEY_GAP) / columns).max(24)
+    }
 
-        // Section: System Info
-        self.draw_text(15, y, b"Informacion de Software:", Color(0x2C3E50));
-        y += 15;
-        self.draw_text(25, y, b"- SO: Go OS v0.2.0 (Alpha)", Color(0x555555));
-        y += 12;
-        self.draw_text(25, y, b"- Kernel: x86_64 Microkernel", Color(0x555555));
-        y += 25;
+    fn calculator_keys_bottom(&self) -> i32 {
+        let rows = crate::calc::KEYPAD.len().div_ceil(crate::calc::KEYPAD_COLUMNS) as i32;
+        CALCULATOR_DISPLAY_H + 20 + rows * (CALCULATOR_KEY_H + CALCULATOR_KEY_GAP)
+    }
 
-        // Section: Memory Info
-        self.draw_text(15, y, b"Memoria RAM (Heap):", Color(0x2C3E50));
-        y += 15;
-        let heap_bytes = crate::allocator::heap_size_bytes();
-        let heap_mib = heap_bytes / (1024 * 1024);
-        let heap_task_reserved = crate::allocator::heap_reserved_bytes();
-        let heap_task_reserved_mib = heap_task_reserved / (1024 * 1024);
-        self.draw_text(
-            25,
-            y,
-            alloc::format!("- Total Reservada: {} MB", heap_mib).as_bytes(),
-            Color(0x555555),
-        );
-        y += 12;
-        self.draw_text(
-            25,
-            y,
-            alloc::format!("- Reservada por tareas: {} MB", heap_task_reserved_mib).as_bytes(),
-            Color(0x555555),
-        );
-        y += 12;
-        self.draw_text(25, y, b"- Estado: Activo", Color(0x555555));
-        y += 25;
+    fn calculator_history_rect(&self) -> Rect {
+        let y = self.calculator_keys_bottom() + 4;
+        let h = (self.content_height() - y - 10).max(30);
+        Rect::new(10, y, self.rect.width.saturating_sub(20), h as u32)
+    }
 
-        // Section: Network Info
-        self.draw_text(15, y, b"Estado de Red:", Color(0x2C3E50));
-        y += 15;
+    /// Keypad buttons in `KEYPAD` order.
+    fn calculator_buttons(&self) -> Vec<Button> {
+        let key_w = self.calculator_key_w();
+        let top = CALCULATOR_DISPLAY_H + 20;
+        crate::calc::KEYPAD
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                let col = (i % crate::calc::KEYPAD_COLUMNS) as i32;
+                let row = (i / crate::calc::KEYPAD_COLUMNS) as i32;
+                let mut button = Button::new(
+                    label,
+                    10 + col * (key_w + CALCULATOR_KEY_GAP),
+                    top + row * (CALCULATOR_KEY_H + CALCULATOR_KEY_GAP),
+                    key_w as u32,
+                    CALCULATOR_KEY_H as u32,
+                );
+                button.bg_color = Color(match *label {
+                    "=" => 0x15803D,
+                    "C" | "<-" => 0xB91C1C,
+                    _ if label.as_bytes()[0].is_ascii_digit() || *label == "." => 0x374151,
+                    _ => 0x1D4ED8,
+                });
+                button
+            })
+            .collect()
+    }
+
+    pub fn render_calculator(&mut self) {
+        if self.kind != WindowKind::Calculator {
+            return;
+        }
+        let content_h = self.content_height();
+        if content_h <= 0 {
+            return;
+        }
+        self.fill_rect(Rect::new(0, 0, self.rect.width, content_h as u32), Color(0x1F2937));
+
+        // Display: the previous calculation or error above, the expression
+        // being typed (or the result) below in large digits.
+        let display = Rect::new(10, 10, self.rect.width.saturating_sub(20), CALCULATOR_DISPLAY_H as u32);
+        self.fill_rect(display, Color(0x0B1220));
+        self.draw_border(display, Color(0x4B5563));
+        let right = display.x + display.width as i32 - 10;
+        let small = if self.calculator.display.starts_with("Error") {
+            self.calculator.display.clone()
+        } else {
+            self.calculator.history.last().cloned().unwrap_or_default()
+        };
+        let small_chars = ((display.width as i32 - 20) / 6).max(4) as usize;
+        let small = Self::trim_label(small.as_str(), small_chars);
+        let small_x = (right - small.len() as i32 * 6).max(display.x + 10);
+        self.draw_text(small_x as u32, 20, small.as_bytes(), Color(0x9CA3AF));
+        let big = if self.calculator.expr.is_empty() {
+            String::from("0")
+        } else {
+            self.calculator.expr.clone()
+        };
+        let big_chars = ((display.width as i32 - 20) / 12).max(4) as usize;
+        // Keep the end of a long expression in view.
+        let start = big
+            .char_indices()
+            .rev()
+            .nth(big_chars.saturating_sub(1))
+            .map_or(0, |(i, _)| i);
+        let big = if big.len() > big_chars { String::from(&big[start..]) } else { big };
+        let big_x = (right - big.len() as i32 * 12).max(display.x + 10);
+        self.draw_text_scaled(big_x as u32, 40, big.as_bytes(), Color(0xF9FAFB), 2);
+
+        for button in self.calculator_buttons().iter() {
+            button.draw(self, button.rect);
+        }
+
+        let history: Vec<String> = self.calculator.history.iter().rev().cloned().collect();
+        let history_rect = self.calculator_history_rect();
+        let mut list = core::mem::take(&mut self.calculator_history);
+        list.rect = history_rect;
+        list.set_lines(&history);
+        list.draw(self, history_rect);
+        self.calculator_history = list;
+        if history.is_empty() {
+            self.draw_text(
+                (history_rect.x + 8) as u32,
+                (history_rect.y + 8) as u32,
+                b"Historial vacio",
+                Color(0x64748B),
+            );
+        }
+    }
+
+    /// A key typed into the calculator; Delete counts as Backspace.
+    pub fn calculator_input(&mut self, key: char) -> bool {
+        if self.kind != WindowKind::Calculator {
+            return false;
+        }
+        let key = if key == '\x7f' { '\x08' } else { key };
+        if !self.calculator.input(key) {
+            return false;
+        }
+        self.render();
+        true
+    }
+
+    pub fn calculator_press(&mut self, label: &str) -> bool {
+        if self.kind != WindowKind::Calculator || !self.calculator.press(label) {
+            return false;
+        }
+        self.render();
+        true
+    }
+
+    fn calendar_cell_w(&self) -> i32 {
+        ((self.rect.width as i32 - 20) / 7).max(20)
+    }
+
+    fn calendar_grid_top(&self) -> i32 {
+        CALENDAR_HEADER_H + 22
+    }
+
+    fn calendar_cell_rect(&self, row: usize, col: usize) -> Rect {
+        let w = self.calendar_cell_w();
+        Rect::new(
+            10 + col as i32 * w,
+            self.calendar_grid_top() + row as i32 * CALENDAR_CELL_H,
+            (w - 2).max(1) as u32,
+            (CALENDAR_CELL_H - 2) as u32,
+        )
+    }
+
+    fn calendar_events_rect(&self) -> Rect {
+        let y = self.calendar_grid_top() + 6 * CALENDAR_CELL_H + 22;
+        let h = (self.content_height() - y - CALENDAR_FOOTER_H).max(30);
+        Rect::new(10, y, self.rect.width.saturating_sub(20), h as u32)
+    }
+
+    fn calendar_input_rect(&self) -> Rect {
+        let y = self.content_height() - CALENDAR_FOOTER_H + 6;
+        Rect::new(10, y, self.rect.width.saturating_sub(96), 22)
+    }
+
+    fn calendar_buttons(&self) -> [(Button, CalendarClickAction); 4] {
+        let delete_x = self.rect.width as i32 - 80;
+        let delete_y = self.content_height() - CALENDAR_FOOTER_H + 6;
+        let mut delete = Button::new("Borrar", delete_x, delete_y, 70, 22);
+        delete.bg_color = Color(0xB91C1C);
+        [
+            (Button::new("<", 10, 8, 32, 24), CalendarClickAction::PrevMonth),
+            (Button::new(">", 46, 8, 32, 24), CalendarClickAction::NextMonth),
+            (Button::new("Hoy", 82, 8, 48, 24), CalendarClickAction::Today),
+            (delete, CalendarClickAction::Delete),
+        ]
+    }
+
+    /// Reload the events of the selected day.
+    pub fn calendar_reload(&mut self) {
+        let rows: Vec<Vec<String>> = crate::calendar::events_on(self.calendar_selected)
+            .iter()
+            .map(|event| alloc::vec![event.time_text(), event.text.clone()])
+            .collect();
+        let selected = self.calendar_events.selected();
+        self.calendar_events.rect = self.calendar_events_rect();
+        self.calendar_events.set_rows(rows);
+        self.calendar_events.select(selected);
+    }
+
+    pub fn render_calendar(&mut self) {
+        if self.kind != WindowKind::Calendar {
+            return;
+        }
+        let content_h = self.content_height();
+        if content_h <= 0 {
+            return;
+        }
+        let date = self.calendar_selected;
+        let today = crate::calendar::today();
+        self.fill_rect(Rect::new(0, 0, self.rect.width, content_h as u32), Color(0x111827));
+        self.fill_rect(Rect::new(0, 0, self.rect.width, CALENDAR_HEADER_H as u32), Color(0x1F2937));
+        for (button, _) in self.calendar_buttons().iter() {
+            button.draw(self, button.rect);
+        }
+        let title = crate::calendar::month_title(date.year, date.month);
+        self.draw_text_scaled(144, 12, title.as_bytes(), Color(0xF9FAFB), 2);
+
+        let cell_w = self.calendar_cell_w();
+        for (col, name) in crate::calendar::WEEKDAY_NAMES.iter().enumerate() {
+            let x = 10 + col as i32 * cell_w + (cell_w - 12) / 2;
+            let color = if col >= 5 { Color(0xF87171) } else { Color(0x9CA3AF) };
+            self.draw_text(x.max(0) as u32, (CALENDAR_HEADER_H + 8) as u32, name.as_bytes(), color);
+        }
+
+        let busy = crate::calendar::busy_days(date.year, date.month);
+        let grid = crate::calendar::month_grid(date.year, date.month);
+        for (row, week) in grid.iter().enumerate() {
+            for (col, day) in week.iter().enumerate() {
+                if *day == 0 {
+                    continue;
+                }
+                let cell = self.calendar_cell_rect(row, col);
+                let is_today = today.year == date.year && today.month == date.month && today.day == *day;
+                let fill = if *day == date.day { 0x1D4ED8 } else { 0x1F2937 };
+                self.fill_rect(cell, Color(fill));
+                if is_today {
+                    self.draw_border(cell, Color(0xFBBF24));
+                }
+                let label = alloc::format!("{}", day);
+                let tx = cell.x + (cell.width as i32 - label.len() as i32 * 6) / 2;
+                self.draw_text(tx.max(0) as u32, (cell.y + 8) as u32, label.as_bytes(), Color(0xF3F4F6));
+                if busy.contains(day) {
+                    let dot = Rect::new(cell.x + cell.width as i32 / 2 - 2, cell.y + cell.height as i32 - 6, 4, 3);
+                    self.fill_rect(dot, Color(0x34D399));
+                }
+            }
+        }
+
+        let events_rect = self.calendar_events_rect();
+        let heading = alloc::format!("Eventos del {:02}/{:02}/{}", date.day, date.month, date.year);
+        self.draw_text(10, (events_rect.y - 14).max(0) as u32, heading.as_bytes(), Color(0xE5E7EB));
+        let mut list = core::mem::take(&mut self.calendar_events);
+        list.rect = events_rect;
+        list.draw(self, events_rect);
+        let empty = list.is_empty();
+        self.calendar_events = list;
+        if empty {
+            self.draw_text(
+                (events_rect.x + 8) as u32,
+                (events_rect.y + 24) as u32,
+                b"Sin eventos este dia",
+                Color(0x64748B),
+            );
+        }
+
+        let input = self.calendar_input_rect();
+        self.fill_rect(input, Color(0xF9FAFB));
+        self.draw_border(input, Color(0x6B7280));
+        let max_chars = ((input.width as i32 - 16) / 6).max(4) as usize;
+        let (text, color) = if self.calendar_input.is_empty() {
+            (String::from("HH:MM texto + Intro para anadir"), Color(0x9CA3AF))
+        } else {
+            let tail = self.calendar_input.len().saturating_sub(max_chars.saturating_sub(1));
+            let mut shown = String::from(self.calendar_input.get(tail..).unwrap_or(""));
+            shown.push('_');
+            (shown, Color(0x111827))
+        };
+        let text = Self::trim_label(text.as_str(), max_chars);
+        self.draw_text((input.x + 6) as u32, (input.y + 7) as u32, text.as_bytes(), color);
+
+        let status_y = content_h - 22;
+        let max_chars = ((self.rect.width as i32 - 16) / 6).max(4) as usize;
+        let status = Self::trim_label(self.calendar_status.as_str(), max_chars);
+        self.draw_text(10, (status_y + 7) as u32, status.as_bytes(), Color(0x9CA3AF));
+    }
+
+    fn calendar_select(&mut self, date: crate::calendar::Date) {
+        if date != self.calendar_selected {
+            self.calendar_selected = date;
+            self.calendar_events.select(None);
+            self.calendar_reload();
+        }
+    }
+
+    pub fn calendar_apply(&mut self, action: CalendarClickAction) -> bool {
+        if self.kind != WindowKind::Calendar {
+            return false;
+        }
+        let date = self.calendar_selected;
+        match action {
+            CalendarClickAction::PrevMonth => self.calendar_select(date.add_months(-1)),
+            CalendarClickAction::NextMonth => self.calendar_select(date.add_months(1)),
+            CalendarClickAction::Today => self.calendar_select(crate::calendar::today()),
+            CalendarClickAction::Day(day) => {
+                if let Some(date) = crate::calendar::Date::new(date.year, date.month, day) {
+                    self.calendar_select(date);
+                }
+            }
+            CalendarClickAction::Event(row) => self.calendar_events.select(Some(row)),
+            CalendarClickAction::Delete => {
+                let Some(row) = self.calendar_events.selected() else {
+                    self.calendar_status = String::from("Selecciona un evento para borrarlo.");
+                    self.render();
+                    return true;
+                };
+                self.calendar_status = match crate::calendar::remove_event(date, row) {
+                    Ok(event) => alloc::format!("Borrado: {}", event.text),
+                    Err(err) => alloc::format!("Error: {}", err),
+                };
+                self.calendar_events.select(None);
+                self.calendar_reload();
+            }
+        }
+        self.render();
+        true
+    }
+
+    /// Arrows move the selected day; typed text builds a new event that
+    /// Enter saves on that day.
+    fn calendar_key(&mut self, special: Option<SpecialKey>, key: Option<char>) -> bool {
+        let date = self.calendar_selected;
+        match (special, key) {
+            (Some(SpecialKey::Left), _) => self.calendar_select(date.add_days(-1)),
+            (Some(SpecialKey::Right), _) => self.calendar_select(date.add_days(1)),
+            (Some(SpecialKey::Up), _) => self.calendar_select(date.add_days(-7)),
+            (Some(SpecialKey::Down), _) => self.calendar_select(date.add_days(7)),
+            (None, Some('\n' | '\r')) => {
+                let input = core::mem::take(&mut self.calendar_input);
+                let input = input.trim();
+                if input.is_empty() {
+                    return false;
+                }
+                let (minute, text) = match input.split_once(' ') {
+                    Some((time, rest)) if crate::calendar::parse_hhmm(time).is_some() => {
+                        (crate::calendar::parse_hhmm(time), rest.trim())
+                    }
+                    _ => (None, input),
+                };
+                self.calendar_status = match crate::calendar::add_event(date, minute, text) {
+                    Ok(()) => alloc::format!("Evento anadido el {}.", date.to_text()),
+                    Err(err) => alloc::format!("Error: {}", err),
+                };
+                self.calendar_reload();
+            }
+            (None, Some('\x08' | '\x7f')) => {
+                if self.calendar_input.pop().is_none() {
+                    return false;
+                }
+            }
+            (None, Some(ch)) if !ch.is_control() => {
+                if self.calendar_input.len() + ch.len_utf8() > crate::calendar::TEXT_MAX_BYTES {
+                    return false;
+                }
+                self.calendar_input.push(ch);
+            }
+            _ => return false,
+        }
+        self.render();
+        true
+    }
+
+    fn clock_list_rect(&self) -> Rect {
+        let h = (self.content_height() - CLOCK_HEADER_H - CLOCK_FOOTER_H).max(40);
+        Rect::new(10, CLOCK_HEADER_H, self.rect.width.saturating_sub(20), h as u32)
+    }
+
+    fn clock_buttons(&self) -> [(Button, ClockClickAction); 5] {
+        let y = self.content_height() - CLOCK_FOOTER_H + 8;
+        let w = ((self.rect.width as i32 - 20 - 4 * 6) / 5).max(40);
+        let button = |i: i32, text: &str, color: u32| {
+            let mut button = Button::new(text, 10 + i * (w + 6), y, w as u32, 24);
+            button.bg_color = Color(color);
+            button
+        };
+        [
+            (button(0, "+1 min", 0x1D4ED8), ClockClickAction::Timer(60_000)),
+            (button(1, "+5 min", 0x1D4ED8), ClockClickAction::Timer(5 * 60_000)),
+            (button(2, "+15 min", 0x1D4ED8), ClockClickAction::Timer(15 * 60_000)),
+            (button(3, "Act./Desact.", 0x0F766E), ClockClickAction::Toggle),
+            (button(4, "Borrar", 0xB91C1C), ClockClickAction::Delete),
+        ]
+    }
+
+    /// Local seconds since the epoch.
+    fn clock_local_seconds() -> i64 {
+        crate::timer::wall_clock_unix_millis().div_euclid(1000)
+            + crate::timer::wall_clock_timezone_offset_minutes() as i64 * 60
+    }
+
+    /// Rows for the alarms followed by the running timers.
+    pub fn rebuild_clock_list(&mut self) {
+        let mut rows: Vec<Vec<String>> = crate::alarm::alarms()
+            .iter()
+            .map(|alarm| {
+                alloc::vec![
+                    crate::calendar::hhmm(alarm.minute),
+                    String::from(alarm.repeat.name()),
+                    String::from(if alarm.enabled { "activa" } else { "apagada" }),
+                    alarm.label.clone(),
+                ]
+            })
+            .collect();
+        for (left, label) in crate::alarm::timers() {
+            rows.push(alloc::vec![
+                crate::alarm::remaining_text(left),
+                String::from("temporizador"),
+                String::from("en marcha"),
+                label,
+            ]);
+        }
+        let selected = self.clock_list.selected();
+        self.clock_list.rect = self.clock_list_rect();
+        self.clock_list.set_rows(rows);
+        self.clock_list.select(selected);
+    }
+
+    pub fn render_clock(&mut self) {
+        if self.kind != WindowKind::Clock {
+            return;
+        }
+        let content_h = self.content_height();
+        if content_h <= 0 {
+            return;
+        }
+        let local_s = Self::clock_local_seconds();
+        self.clock_shown_second = local_s;
+        self.fill_rect(Rect::new(0, 0, self.rect.width, content_h as u32), Color(0x0F172A));
+
+        let day_s = local_s.rem_euclid(86_400);
+        let face = alloc::format!("{:02}:{:02}:{:02}", day_s / 3600, day_s / 60 % 60, day_s % 60);
+        let face_x = (self.rect.width as i32 - face.len() as i32 * 24) / 2;
+        self.draw_text_scaled(face_x.max(0) as u32, 10, face.as_bytes(), Color(0xF9FAFB), 4);
+        let (date, _) = crate::calendar::local_now();
+        let line = alloc::format!(
+            "{} {} de {} de {}",
+            crate::calendar::WEEKDAY_NAMES[date.weekday()],
+            date.day,
+            crate::calendar::MONTH_NAMES[(date.month - 1) as usize],
+            date.year
+        );
+        let line_x = (self.rect.width as i32 - line.len() as i32 * 6) / 2;
+        self.draw_text(line_x.max(0) as u32, 52, line.as_bytes(), Color(0x94A3B8));
+
+        let list_rect = self.clock_list_rect();
+        let mut list = core::mem::take(&mut self.clock_list);
+        list.rect = list_rect;
+        list.draw(self, list_rect);
+        let empty = list.is_empty();
+        self.clock_list = list;
+        if empty {
+            self.draw_text(
+                (list_rect.x + 8) as u32,
+                (list_rect.y + 24) as u32,
+                b"Sin alarmas. Usa 'alarm add HH:MM [diario] texto'.",
+                Color(0x64748B),
+            );
+        }
+
+        for (button, _) in self.clock_buttons().iter() {
+            button.draw(self, button.rect);
+        }
+        let status_y = content_h - 22;
+        let max_chars = ((self.rect.width as i32 - 16) / 6).max(4) as usize;
+        let status = Self::trim_label(self.clock_status.as_str(), max_chars);
+        self.draw_text(10, (status_y + 7) as u32, status.as_bytes(), Color(0x94A3B8));
+    }
+
+    pub fn clock_apply(&mut self, action: ClockClickAction) -> bool {
+        if self.kind != WindowKind::Clock {
+            return false;
+        }
+        let alarm_count = crate::alarm::alarms().len();
+        let selected = self.clock_list.selected();
+        match action {
+            ClockClickAction::Select(row) => self.clock_list.select(Some(row)),
+            ClockClickAction::Timer(ms) => {
+                self.clock_status = match crate::alarm::start_timer(ms, "Temporizador") {
+                    Ok(()) => alloc::format!("Temporizador de {}.", crate::alarm::remaining_text(ms)),
+                    Err(err) => alloc::format!("Error: {}", err),
+                };
+            }
+            ClockClickAction::Toggle => {
+                self.clock_status = match selected {
+                    Some(row) if row < alarm_count => {
+                        let enabled = crate::alarm::alarms().get(row).is_some_and(|a| a.enabled);
+                        match crate::alarm::set_enabled(row, !enabled) {
+                            Ok(()) if enabled => String::from("Alarma apagada."),
+                            Ok(()) => String::from("Alarma activada."),
+                            Err(err) => alloc::format!("Error: {}", err),
+                        }
+                    }
+                    _ => String::from("Selecciona una alarma."),
+                };
+            }
+            ClockClickAction::Delete => {
+                self.clock_status = match selected {
+                    Some(row) if row < alarm_count => match crate::alarm::remove_alarm(row) {
+                        Ok(()) => String::from("Alarma borrada."),
+                        Err(err) => alloc::format!("Error: {}", err),
+                    },
+                    Some(row) if crate::alarm::cancel_timer(row - alarm_count) => {
+                        String::from("Temporizador cancelado.")
+                    }
+                    _ => String::from("Selecciona una alarma o un temporizador."),
+                };
+                self.clock_list.select(None);
+            }
+        }
+        self.rebuild_clock_list();
+        self.render();
+        true
+    }
+
+    /// Redraw once the local second changes. Returns whether it did.
+    pub fn clock_refresh(&mut self) -> bool {
+        if self.kind != WindowKind::Clock || Self::clock_local_seconds() == self.clock_shown_second {
+            return false;
+        }
+        self.rebuild_clock_list();
+        self.render();
+        true
+    }
+
+    /// Keyboard input for the calculator, calendar, clock and recorder applets.
+    pub fn applet_key(&mut self, special: Option<SpecialKey>, key: Option<char>) -> bool {
+        match self.kind {
+            WindowKind::Recorder if key == Some(' ') => {
+                let action = if crate::capture::recording() {
+                    RecorderClickAction::Stop
+                } else {
+                    RecorderClickAction::Record
+                };
+                self.recorder_apply(action)
+            }
+            WindowKind::Calculator => key.is_some_and(|key| self.calculator_input(key)),
+            WindowKind::Calendar => self.calendar_key(special, key),
+            WindowKind::Clock => {
+                self.clock_list.rect = self.clock_list_rect();
+                let changed = match special {
+                    Some(SpecialKey::Up) => self.clock_list.move_selection(-1),
+                    Some(SpecialKey::Down) => self.clock_list.move_selection(1),
+                    _ => false,
+                };
+                if changed {
+                    self.render();
+                }
+                changed
+            }
+            _ => false,
+        }
+    }
+
+    fn recorder_buttons(&self) -> [(Button, RecorderClickAction); 2] {
+        let y = self.content_height() - RECORDER_FOOTER_H + 8;
+        let w = ((self.rect.width as i32 - 20 - 6) / 2).max(40);
+        let recording = crate::capture::recording();
+        let mut record = Button::new("Grabar", 10, y, w as u32, 24);
+        record.bg_color = Color(if recording { 0x7F1D1D } else { 0xB91C1C });
+        let mut stop = Button::new("Detener y guardar", 16 + w, y, w as u32, 24);
+        stop.bg_color = Color(if recording { 0x1D4ED8 } else { 0x475569 });
+        [(record, RecorderClickAction::Record), (stop, RecorderClickAction::Stop)]
+    }
+
+    /// Level meter bar from `capture::METER_FLOOR_DB` to 0 dBFS.
+    fn draw_level_meter(&mut self, x: i32, y: i32, width: u32, db: Option<i32>) {
+        self.fill_rect(Rect::new(x, y, width, 10), Color(0x1E293B));
+        let Some(db) = db else {
+            return;
+        };
+        let filled = crate::capture::meter_percent(db) * width / 100;
+        let color = if db >= -3 {
+            Color(0xDC2626)
+        } else if db >= -12 {
+            Color(0xF59E0B)
+        } else {
+            Color(0x22C55E)
+        };
+        self.fill_rect(Rect::new(x, y, filled, 10), color);
+    }
+
+    pub fn render_recorder(&mut self) {
+        if self.kind != WindowKind::Recorder {
+            return;
+        }
+        let content_h = self.content_height();
+        if content_h <= 0 {
+            return;
+        }
+        self.fill_rect(Rect::new(0, 0, self.rect.width, content_h as u32), Color(0x0F172A));
+
+        let elapsed_ms = crate::capture::recording_ms();
+        let level = crate::capture::level_db();
+        self.recorder_shown = (elapsed_ms.map_or(u64::MAX, |ms| ms / 1000), level.unwrap_or(0));
+        let face = crate::wav::format_time(elapsed_ms.unwrap_or(0) as u32);
+        let face_x = (self.rect.width as i32 - face.len() as i32 * 24) / 2;
+        let face_color = if elapsed_ms.is_some() {
+            Color(0xF87171)
+        } else {
+            Color(0xF9FAFB)
+        };
+        self.draw_text_scaled(face_x.max(0) as u32, 14, face.as_bytes(), face_color, 4);
+
+        let state = if !crate::capture::available() {
+            String::from("Sin microfono")
+        } else if elapsed_ms.is_some() {
+            alloc::format!(
+                "Grabando ({} max.)",
+                crate::wav::format_time(crate::capture::RECORD_MAX_SECONDS * 1000)
+            )
+        } else {
+            String::from(crate::audio::capture_input_name())
+        };
+        let state_x = (self.rect.width as i32 - state.len() as i32 * 6) / 2;
+        self.draw_text(state_x.max(0) as u32, 60, state.as_bytes(), Color(0x94A3B8));
+
+        let meter_w = self.rect.width.saturating_sub(80);
+        self.draw_level_meter(10, RECORDER_METER_Y, meter_w, level);
+        let level_text = match level {
+            Some(db) => alloc::format!("{} dB", db),
+            None => String::from("-- dB"),
+        };
+        self.draw_text(
+            (20 + meter_w) as u32,
+            (RECORDER_METER_Y + 1) as u32,
+            level_text.as_bytes(),
+            Color(0x94A3B8),
+        );
+
+        for (button, _) in self.recorder_buttons().iter() {
+            button.draw(self, button.rect);
+        }
+        let status_y = content_h - 22;
+        let max_chars = ((self.rect.width as i32 - 16) / 6).max(4) as usize;
+        let status = Self::trim_label(self.recorder_status.as_str(), max_chars);
+        self.draw_text(10, (status_y + 7) as u32, status.as_bytes(), Color(0x94A3B8));
+    }
+
+    pub fn recorder_apply(&mut self, action: RecorderClickAction) -> bool {
+        if self.kind != WindowKind::Recorder {
+            return false;
+        }
+        self.recorder_status = match action {
+            RecorderClickAction::Record => match crate::capture::start_recording() {
+                Ok(()) => String::from("Grabando..."),
+                Err(err) => alloc::format!("Error: {}", err),
+            },
+            RecorderClickAction::Stop => match crate::capture::stop_recording() {
+                Ok(path) => alloc::format!("Guardado en {}", path),
+                Err(err) => alloc::format!("Error: {}", err),
+            },
+        };
+        self.render();
+        true
+    }
+
+    /// Redraw Settings when the microphone level on its meter changes.
+    pub fn settings_level_refresh(&mut self) -> bool {
+        if self.kind != WindowKind::Settings || crate::capture::level_db() == self.settings_shown_level {
+            return false;
+        }
+        self.render();
+        true
+    }
+
+    /// Redraw when the elapsed second or the input level changes.
+    pub fn recorder_refresh(&mut self) -> bool {
+        if self.kind != WindowKind::Recorder {
+            return false;
+        }
+        let elapsed_s = crate::capture::recording_ms().map_or(u64::MAX, |ms| ms / 1000);
+        let level = crate::capture::level_db().unwrap_or(0);
+        if (elapsed_s, level) == self.recorder_shown {
+            return false;
+        }
+        self.render();
+        true
+    }
+
+    /// Track and knob of a Settings slider row; `value` runs from 0 to `range`.
+    fn draw_settings_slider(&mut self, row_y: i32, value: u32, range: u32) {
+        let track_y = row_y + 3;
+        self.fill_rect(
+            Rect::new(SETTINGS_SLIDER_X, track_y, SETTINGS_SLIDER_W as u32, 3),
+            Color(0xB8C4D0),
+        );
+        let filled = (value.min(range) as i64 * SETTINGS_SLIDER_W as i64 / range.max(1) as i64) as i32;
+        self.fill_rect(Rect::new(SETTINGS_SLIDER_X, track_y, filled as u32, 3), Color(0x2980B9));
+        self.fill_rect(Rect::new(SETTINGS_SLIDER_X + filled - 3, row_y - 1, 6, 10), Color(0x34495E));
+    }
+
+    pub fn render_settings(&mut self) {
+        if self.kind != WindowKind::Settings {
+            return;
+        }
+
+        let content_h = self.content_height();
+        if content_h <= 0 {
+            return;
+        }
+
+        // Background
+        self.fill_rect(Rect::new(0, 0, self.rect.width, content_h as u32), Color(0xF2F2F2));
+
+        // Header
+        self.fill_rect(Rect::new(0, 0, self.rect.width, 40), Color(0x34495E));
+        self.draw_text(15, 15, crate::i18n::tr("settings.title").as_bytes(), Color(0xFFFFFF));
+
+        let mut y = 60;
+
+        // Section: Display color
+        self.draw_text(15, y, crate::i18n::tr("settings.display").as_bytes(), Color(0x2C3E50));
+        let (gamma_pct, temperature, night, night_active) = crate::gamma::settings_snapshot();
+        let gamma_label = alloc::format!("{}.{:02}", gamma_pct / 100, gamma_pct % 100);
+        self.draw_text(
+            25,
+            SETTINGS_GAMMA_ROW_Y as u32,
+            crate::i18n::trf("settings.gamma", &[&gamma_label]).as_bytes(),
+            Color(0x555555),
+        );
+        self.draw_settings_slider(
+            SETTINGS_GAMMA_ROW_Y,
+            gamma_pct - crate::gamma::GAMMA_MIN,
+            crate::gamma::GAMMA_MAX - crate::gamma::GAMMA_MIN,
+        );
+        self.draw_text(
+            25,
+            SETTINGS_TEMPERATURE_ROW_Y as u32,
+            crate::i18n::trf("settings.temperature", &[&temperature]).as_bytes(),
+            Color(0x555555),
+        );
+        self.draw_settings_slider(
+            SETTINGS_TEMPERATURE_ROW_Y,
+            temperature - crate::gamma::TEMPERATURE_MIN,
+            crate::gamma::NEUTRAL_TEMPERATURE - crate::gamma::TEMPERATURE_MIN,
+        );
+        let mut night_label = match night {
+            crate::gamma::NightMode::Off => crate::i18n::tr("settings.night_off"),
+            crate::gamma::NightMode::On => crate::i18n::tr("settings.night_on"),
+            crate::gamma::NightMode::Schedule => {
+                let (from, to) = crate::gamma::night_window();
+                crate::i18n::trf(
+                    "settings.night_schedule",
+                    &[&crate::gamma::format_hhmm(from), &crate::gamma::format_hhmm(to)],
+                )
+            }
+        };
+        if night_active {
+            night_label.push_str(crate::i18n::tr("settings.night_active").as_str());
+        }
+        self.draw_text(
+            25,
+            SETTINGS_NIGHT_ROW_Y as u32,
+            crate::i18n::trf("settings.night_light", &[&night_label]).as_bytes(),
+            if night_active { Color(0xD35400) } else { Color(0x555555) },
+        );
+        y = (SETTINGS_NIGHT_ROW_Y + 25) as u32;
+
+        // Section: System Info
+        self.draw_text(15, y, crate::i18n::tr("settings.software").as_bytes(), Color(0x2C3E50));
+        y += 15;
+        self.draw_text(25, y, b"- SO: Go OS v0.2.0 (Alpha)", Color(0x555555));
+        y += 12;
+        self.draw_text(25, y, b"- Kernel: x86_64 Microkernel", Color(0x555555));
+        y += 25;
+
+        // Section: Config registry
+        self.draw_text(15, y, crate::i18n::tr("settings.registry").as_bytes(), Color(0x2C3E50));
+        y += 15;
+        let storage = if crate::config::is_persistent() {
+            String::from("\\REDUXOS\\CONFIG.BIN")
+        } else {
+            crate::i18n::tr("settings.memory_only")
+        };
+        self.draw_text(
+            25,
+            y,
+            crate::i18n::trf(
+                "settings.registry_keys",
+                &[&crate::config::key_count(), &crate::config::revision(""), &storage],
+            )
+            .as_bytes(),
+            Color(0x555555),
+        );
+        y += 12;
+        self.draw_text(
+            25,
+            y,
+            crate::i18n::trf("settings.language", &[&crate::i18n::current_code()]).as_bytes(),
+            Color(0x555555),
+        );
+        y += 12;
+        self.draw_text(
+            25,
+            y,
+            crate::i18n::trf(
+                "settings.scale",
+                &[&crate::gui::hidpi::format_scale(crate::framebuffer::scale_percent())],
+            )
+            .as_bytes(),
+            Color(0x555555),
+        );
+        y += 25;
+
+        // Section: Memory Info
+        self.draw_text(15, y, crate::i18n::tr("settings.memory").as_bytes(), Color(0x2C3E50));
+        y += 15;
+        let heap_size = crate::i18n::format::size(crate::allocator::heap_size_bytes() as u64);
+        let heap_task_reserved = crate::i18n::format::size(crate::allocator::heap_reserved_bytes() as u64);
+        self.draw_text(
+            25,
+            y,
+            alloc::format!("- Total Reservada: {}", heap_size).as_bytes(),
+            Color(0x555555),
+        );
+        y += 12;
+        self.draw_text(
+            25,
+            y,
+            alloc::format!("- Reservada por tareas: {}", heap_task_reserved).as_bytes(),
+            Color(0x555555),
+        );
+        y += 12;
+        self.draw_text(25, y, b"- Estado: Activo", Color(0x555555));
+        y += 25;
+
+        // Section: Sound input
+        self.draw_text(15, y, crate::i18n::tr("settings.sound").as_bytes(), Color(0x2C3E50));
+        y += 15;
+        let input = if crate::capture::available() {
+            crate::i18n::trf("settings.sound_input", &[&crate::audio::capture_input_name()])
+        } else {
+            crate::i18n::tr("settings.sound_none")
+        };
+        self.draw_text(25, y, input.as_bytes(), Color(0x555555));
+        y += 12;
+        let level = crate::capture::level_db();
+        let level_text = level.map_or_else(|| String::from("--"), |db| alloc::format!("{}", db));
+        self.draw_text(
+            25,
+            y,
+            crate::i18n::trf("settings.sound_level", &[&level_text]).as_bytes(),
+            Color(0x555555),
+        );
+        self.draw_level_meter(SETTINGS_SLIDER_X, y as i32 - 1, SETTINGS_SLIDER_W as u32, level);
+        self.settings_shown_level = level;
+        y += 25;
+
+        // Section: Network Info
+        self.draw_text(15, y, crate::i18n::tr("settings.network").as_bytes(), Color(0x2C3E50));
+        y += 15;
         
-        let has_net = unsafe { crate::net::IFACE.is_some() };
+        let has_net = unsafe { crate::net::IFACE.get().is_some() };
         let intel_model = crate::intel_net::get_model_name();
         let link_up = crate::intel_net::is_link_up();
         let active_transport = crate::net::get_active_transport();
@@ -4473,7 +5950,7 @@ Tab DOCS is read-only.\n"
             }
 
             // IP Address info
-            let dhcp_status = unsafe { crate::net::DHCP_STATUS };
+            let dhcp_status = crate::net::dhcp_status();
             self.draw_text(25, y, alloc::format!("- DHCP: {}", dhcp_status).as_bytes(), Color(0x34495E));
             y += 12;
 
@@ -4518,7 +5995,7 @@ Tab DOCS is read-only.\n"
         } else if has_net {
             self.draw_text(25, y, b"- Interfaz: VirtIO Ethernet", Color(0x27AE60));
             y += 12;
-            let dhcp_status = unsafe { crate::net::DHCP_STATUS };
+            let dhcp_status = crate::net::dhcp_status();
             self.draw_text(25, y, alloc::format!("- DHCP: {}", dhcp_status).as_bytes(), Color(0x555555));
             y += 12;
             if let Some(ip) = crate::net::get_ip_address() {
@@ -4852,7 +6329,7 @@ Tab DOCS is read-only.\n"
                 frame_size
             }
             _ => {
-                let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+                let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
                 fat.read_file_range(
                     self.video_player_file_cluster,
                     self.video_player_file_size as usize,
@@ -5137,6 +6614,304 @@ Tab DOCS is read-only.\n"
         self.draw_text(24, (status_y + 10) as u32, status_trim.as_bytes(), Color(0x9CA3AF));
     }
 
+    fn task_manager_list_rect(&self) -> Rect {
+        let y = TASK_MGR_HEADER_H + TASK_MGR_GRAPH_H + 6;
+        let h = (self.content_height() - y - TASK_MGR_FOOTER_H).max(60);
+        Rect::new(10, y, self.rect.width.saturating_sub(20), h as u32)
+    }
+
+    fn task_manager_graph_rects(&self) -> [Rect; 3] {
+        let gap = 10;
+        let w = ((self.rect.width as i32 - 20 - gap * 2) / 3).max(60);
+        let y = TASK_MGR_HEADER_H + 6;
+        let h = (TASK_MGR_GRAPH_H - 6) as u32;
+        [
+            Rect::new(10, y, w as u32, h),
+            Rect::new(10 + w + gap, y, w as u32, h),
+            Rect::new(10 + (w + gap) * 2, y, w as u32, h),
+        ]
+    }
+
+    fn task_manager_buttons(&self) -> [(Rect, TaskManagerClickAction, u32, &'static str); 5] {
+        let footer_y = self.content_height() - TASK_MGR_FOOTER_H;
+        let btn_w = ((self.rect.width as i32 - 44) / 3).max(100);
+        let btn_h = 22;
+        let col = |i: i32| 12 + i * (btn_w + 10);
+        let row1_y = footer_y + 8;
+        let row2_y = row1_y + btn_h + 8;
+        let rect = |x: i32, y: i32| Rect::new(x, y, btn_w as u32, btn_h as u32);
+        [
+            (rect(col(0), row1_y), TaskManagerClickAction::Kill, 0xB91C1C, "Terminar"),
+            (rect(col(1), row1_y), TaskManagerClickAction::CancelInstall, 0x9A3412, "Cancelar Install"),
+            (rect(col(2), row1_y), TaskManagerClickAction::CancelFs, 0x9A3412, "Cancelar FS"),
+            (rect(col(0), row2_y), TaskManagerClickAction::CancelPaste, 0x7C2D12, "Cancelar Paste"),
+            (rect(col(1), row2_y), TaskManagerClickAction::CancelAll, 0x991B1B, "Cancelar Todo"),
+        ]
+    }
+
+    /// Rebuild the list rows from the latest processes and jobs, keeping the
+    /// selected target selected.
+    pub fn rebuild_task_manager_list(&mut self) {
+        use crate::sysmon::SortKey;
+        let selected = self.task_manager_selected_target();
+        let mut processes = self.task_manager_processes.clone();
+        crate::sysmon::sort_rows(&mut processes, self.task_manager_sort, self.task_manager_sort_ascending);
+
+        let mut rows = Vec::with_capacity(processes.len() + self.task_manager_jobs.len());
+        let mut targets = Vec::with_capacity(rows.capacity());
+        for p in processes.iter() {
+            rows.push(alloc::vec![
+                alloc::format!("{}", p.pid),
+                p.name.clone(),
+                String::from(if p.ring == crate::process::RingLevel::Kernel { "R0" } else { "R3" }),
+                String::from(if p.active { "activo" } else { "terminado" }),
+                alloc::format!("{}", p.threads),
+                alloc::format!("{}.{}", p.cpu_permille / 10, p.cpu_permille % 10),
+                crate::perf::format_bytes(p.mem_bytes),
+                crate::perf::format_bytes(p.net_bytes),
+            ]);
+            targets.push(TaskManagerTarget::Process(p.pid));
+        }
+        for (target, cells) in self.task_manager_jobs.iter() {
+            rows.push(cells.clone());
+            targets.push(*target);
+        }
+
+        // Mark the sort column in its header.
+        for (i, column) in self.task_manager_list.columns.iter_mut().enumerate() {
+            let key = Self::task_manager_sort_key(i);
+            let base = match i {
+                5 => "CPU%",
+                _ => key.map(SortKey::label).unwrap_or(""),
+            };
+            if key == Some(self.task_manager_sort) {
+                let arrow = if self.task_manager_sort_ascending { "^" } else { "v" };
+                column.title = alloc::format!("{} {}", base, arrow);
+            } else if key.is_some() {
+                column.title = String::from(base);
+            }
+        }
+
+        self.task_manager_list.rect = self.task_manager_list_rect();
+        self.task_manager_list.set_rows(rows);
+        self.task_manager_targets = targets;
+        // Only re-select when the row moved, so a refresh does not undo the
+        // user's scrolling.
+        let row = selected.and_then(|t| self.task_manager_targets.iter().position(|x| *x == t));
+        if row != self.task_manager_list.selected() {
+            self.task_manager_list.select(row);
+        }
+    }
+
+    fn task_manager_sort_key(column: usize) -> Option<crate::sysmon::SortKey> {
+        use crate::sysmon::SortKey;
+        match column {
+            0 => Some(SortKey::Pid),
+            1 => Some(SortKey::Name),
+            5 => Some(SortKey::Cpu),
+            6 => Some(SortKey::Memory),
+            7 => Some(SortKey::Net),
+            _ => None,
+        }
+    }
+
+    pub fn task_manager_selected_target(&self) -> Option<TaskManagerTarget> {
+        self.task_manager_list
+            .selected()
+            .and_then(|row| self.task_manager_targets.get(row).copied())
+    }
+
+    /// Sort by `key`; picking the current key again flips the direction.
+    pub fn task_manager_set_sort(&mut self, key: crate::sysmon::SortKey) {
+        if self.task_manager_sort == key {
+            self.task_manager_sort_ascending = !self.task_manager_sort_ascending;
+        } else {
+            self.task_manager_sort = key;
+            self.task_manager_sort_ascending = matches!(key, crate::sysmon::SortKey::Pid | crate::sysmon::SortKey::Name);
+        }
+        self.rebuild_task_manager_list();
+        self.render();
+    }
+
+    pub fn task_manager_move_selection(&mut self, delta: i32) -> bool {
+        if self.kind != WindowKind::TaskManager {
+            return false;
+        }
+        self.task_manager_list.rect = self.task_manager_list_rect();
+        let changed = self.task_manager_list.move_selection(delta);
+        if changed {
+            self.render();
+        }
+        changed
+    }
+
+    fn boot_entries_list_rect(&self) -> Rect {
+        let y = BOOT_ENTRIES_HEADER_H + 6;
+        let h = (self.content_height() - y - BOOT_ENTRIES_PREVIEW_H - BOOT_ENTRIES_FOOTER_H).max(60);
+        Rect::new(10, y, self.rect.width.saturating_sub(20), h as u32)
+    }
+
+    fn boot_entries_preview_rect(&self) -> Rect {
+        let y = self.content_height() - BOOT_ENTRIES_FOOTER_H - BOOT_ENTRIES_PREVIEW_H + 4;
+        Rect::new(10, y, self.rect.width.saturating_sub(20), (BOOT_ENTRIES_PREVIEW_H - 8) as u32)
+    }
+
+    fn boot_entries_buttons(&self) -> [(Rect, BootEntriesClickAction, u32, &'static str); 10] {
+        let footer_y = self.content_height() - BOOT_ENTRIES_FOOTER_H;
+        let btn_w = ((self.rect.width as i32 - 64) / 5).max(80);
+        let btn_h = 22;
+        let col = |i: i32| 12 + i * (btn_w + 10);
+        let row1_y = footer_y + 8;
+        let row2_y = row1_y + btn_h + 8;
+        let rect = |x: i32, y: i32| Rect::new(x, y, btn_w as u32, btn_h as u32);
+        [
+            (rect(col(0), row1_y), BootEntriesClickAction::Up, 0x1D4ED8, "Subir"),
+            (rect(col(1), row1_y), BootEntriesClickAction::Down, 0x1D4ED8, "Bajar"),
+            (rect(col(2), row1_y), BootEntriesClickAction::Toggle, 0x0F766E, "Activar/Desact."),
+            (rect(col(3), row1_y), BootEntriesClickAction::Next, 0x0F766E, "BootNext"),
+            (rect(col(4), row1_y), BootEntriesClickAction::Delete, 0xB91C1C, "Borrar"),
+            (rect(col(0), row2_y), BootEntriesClickAction::Apply, 0x15803D, "Aplicar"),
+            (rect(col(1), row2_y), BootEntriesClickAction::Discard, 0x374151, "Descartar"),
+            (rect(col(2), row2_y), BootEntriesClickAction::Reload, 0x374151, "Recargar"),
+            (rect(col(3), row2_y), BootEntriesClickAction::Restore, 0x9A3412, "Restaurar copia"),
+            (rect(col(4), row2_y), BootEntriesClickAction::Clean, 0xB91C1C, "Limpiar"),
+        ]
+    }
+
+    /// Read the boot variables again and drop the staged changes.
+    pub fn reload_boot_entries(&mut self) {
+        match crate::bootvar::read_state() {
+            Ok(state) => {
+                self.boot_entries_plan = crate::bootvar::Plan::new(&state);
+                self.boot_entries_state = state;
+                self.boot_entries_status = String::from("Sin cambios pendientes.");
+            }
+            Err(err) => {
+                self.boot_entries_state = crate::bootvar::State::default();
+                self.boot_entries_plan = crate::bootvar::Plan::default();
+                self.boot_entries_status = alloc::format!("Error: {}", err);
+            }
+        }
+        self.rebuild_boot_entries_list();
+    }
+
+    /// Rows for the staged plan, keeping the selected entry selected.
+    pub fn rebuild_boot_entries_list(&mut self) {
+        let selected = self.boot_entries_selected();
+        let rows = crate::bootvar::rows(&self.boot_entries_state, &self.boot_entries_plan);
+        self.boot_entries_ids = rows.iter().map(|(id, _)| *id).collect();
+        self.boot_entries_list.rect = self.boot_entries_list_rect();
+        self.boot_entries_list.set_rows(rows.into_iter().map(|(_, cells)| cells).collect());
+        let row = selected.and_then(|id| self.boot_entries_ids.iter().position(|x| *x == id));
+        self.boot_entries_list.select(row);
+    }
+
+    pub fn boot_entries_selected(&self) -> Option<u16> {
+        self.boot_entries_list
+            .selected()
+            .and_then(|row| self.boot_entries_ids.get(row).copied())
+    }
+
+    pub fn boot_entries_move_selection(&mut self, delta: i32) -> bool {
+        if self.kind != WindowKind::BootEntries {
+            return false;
+        }
+        self.boot_entries_list.rect = self.boot_entries_list_rect();
+        let changed = self.boot_entries_list.move_selection(delta);
+        if changed {
+            self.render();
+        }
+        changed
+    }
+
+    pub fn render_boot_entries(&mut self) {
+        if self.kind != WindowKind::BootEntries {
+            return;
+        }
+
+        let content_h = self.content_height();
+        if content_h <= 0 {
+            return;
+        }
+        let content_h_i32 = content_h as i32;
+
+        self.fill_rect(Rect::new(0, 0, self.rect.width, content_h as u32), Color(0x111827));
+        self.fill_rect(Rect::new(0, 0, self.rect.width, BOOT_ENTRIES_HEADER_H as u32), Color(0x1F2937));
+        self.draw_text(14, 14, b"ENTRADAS DE ARRANQUE UEFI", Color(0xF9FAFB));
+        if !self.boot_entries_status.is_empty() {
+            let status_trim = Self::trim_label(self.boot_entries_status.as_str(), 72);
+            self.draw_text(14, 28, status_trim.as_bytes(), Color(0x9CA3AF));
+        }
+
+        let list_rect = self.boot_entries_list_rect();
+        let mut list = core::mem::take(&mut self.boot_entries_list);
+        list.rect = list_rect;
+        list.draw(self, list_rect);
+        self.boot_entries_list = list;
+
+        // Dry run of the staged changes.
+        let preview = self.boot_entries_preview_rect();
+        self.fill_rect(preview, Color(0x0F172A));
+        self.draw_border(preview, Color(0x334155));
+        let max_chars = ((preview.width as i32 - 16) / 8).max(8) as usize;
+        let mut lines = match self.boot_entries_plan.check(&self.boot_entries_state) {
+            Ok(()) => self.boot_entries_plan.changes(&self.boot_entries_state),
+            Err(err) => alloc::vec![alloc::format!("No se puede aplicar: {}", err)],
+        };
+        if lines.is_empty() {
+            lines.push(String::from("Vista previa: sin cambios."));
+        } else {
+            lines.insert(0, String::from("Vista previa (Aplicar guarda antes una copia):"));
+        }
+        let visible = ((preview.height as i32 - 8) / 14).max(1) as usize;
+        for (row, line) in lines.iter().take(visible).enumerate() {
+            let text = Self::trim_label(line.as_str(), max_chars);
+            let color = if row == 0 { 0x93C5FD } else { 0xE5E7EB };
+            self.draw_text(
+                (preview.x + 8) as u32,
+                (preview.y + 6 + row as i32 * 14) as u32,
+                text.as_bytes(),
+                Color(color),
+            );
+        }
+
+        for (rect, _, color, label) in self.boot_entries_buttons().iter() {
+            if rect.y + rect.height as i32 <= content_h_i32 {
+                self.fill_rect(*rect, Color(*color));
+                self.draw_border(*rect, Color(0x1F2937));
+                let text = Self::trim_label(label, 18);
+                self.draw_text((rect.x + 8) as u32, (rect.y + 6) as u32, text.as_bytes(), Color(0xF8FAFC));
+            }
+        }
+    }
+
+    /// Bars for the last samples; each value is a pair stacked bottom-up.
+    fn draw_task_manager_graph(&mut self, rect: Rect, title: &str, values: &[(u64, u64)], max: u64, colors: (u32, u32)) {
+        self.fill_rect(rect, Color(0x0F172A));
+        self.draw_border(rect, Color(0x334155));
+        let text = Self::trim_label(title, ((rect.width as usize) / 6).saturating_sub(2));
+        self.draw_text((rect.x + 5) as u32, (rect.y + 5) as u32, text.as_bytes(), Color(0xE2E8F0));
+
+        let plot = Rect::new(rect.x + 4, rect.y + 16, rect.width.saturating_sub(8), rect.height.saturating_sub(20));
+        let slots = crate::sysmon::HISTORY_LEN as u32;
+        let bar_w = (plot.width / slots).max(1);
+        let max = max.max(1);
+        let shown = values.len().min((plot.width / bar_w) as usize);
+        let right = plot.x + plot.width as i32;
+        for (i, &(a, b)) in values[values.len() - shown..].iter().rev().enumerate() {
+            let x = right - (i as i32 + 1) * bar_w as i32;
+            let ha = (a.min(max) * plot.height as u64 / max) as u32;
+            let hb = (b.min(max - a.min(max)) * plot.height as u64 / max) as u32;
+            let base = plot.y + plot.height as i32;
+            if ha > 0 {
+                self.fill_rect(Rect::new(x, base - ha as i32, bar_w, ha), Color(colors.0));
+            }
+            if hb > 0 {
+                self.fill_rect(Rect::new(x, base - (ha + hb) as i32, bar_w, hb), Color(colors.1));
+            }
+        }
+    }
+
     pub fn render_task_manager(&mut self) {
         if self.kind != WindowKind::TaskManager {
             return;
@@ -5147,7 +6922,6 @@ Tab DOCS is read-only.\n"
             return;
         }
 
-        let w = self.rect.width as i32;
         let content_h_i32 = content_h as i32;
 
         // Background
@@ -5160,13 +6934,73 @@ Tab DOCS is read-only.\n"
         self.fill_rect(Rect::new(0, 0, self.rect.width, TASK_MGR_HEADER_H as u32), Color(0x1F2937));
         self.draw_text(14, 14, b"TASK MANAGER", Color(0xF9FAFB));
         if !self.task_manager_status.is_empty() {
-            let status_trim = Self::trim_label(self.task_manager_status.as_str(), 48);
+            let status_trim = Self::trim_label(self.task_manager_status.as_str(), 72);
             self.draw_text(14, 28, status_trim.as_bytes(), Color(0x9CA3AF));
         }
 
-        // List area
-        let list_y = TASK_MGR_HEADER_H + 6;
-        let list_h = (content_h_i32 - TASK_MGR_HEADER_H - TASK_MGR_FOOTER_H).max(60);
+        // Graphs: total CPU, heap, network
+        let history = core::mem::take(&mut self.task_manager_history);
+        let latest = history.last().copied().unwrap_or_default();
+        let [cpu_rect, heap_rect, net_rect] = self.task_manager_graph_rects();
+        let cpu: Vec<(u64, u64)> = history.iter().map(|s| (s.cpu_permille as u64, 0)).collect();
+        let cpu_title = alloc::format!("CPU {}.{}%", latest.cpu_permille / 10, latest.cpu_permille % 10);
+        self.draw_task_manager_graph(cpu_rect, cpu_title.as_str(), cpu.as_slice(), 1000, (0x3B82F6, 0x3B82F6));
+        let heap: Vec<(u64, u64)> = history.iter().map(|s| (s.heap_used as u64, 0)).collect();
+        let heap_title = alloc::format!(
+            "Heap {}/{} MiB",
+            latest.heap_used / (1024 * 1024),
+            latest.heap_total / (1024 * 1024)
+        );
+        self.draw_task_manager_graph(heap_rect, heap_title.as_str(), heap.as_slice(), latest.heap_total as u64, (0x10B981, 0x10B981));
+        let net: Vec<(u64, u64)> = history.iter().map(|s| (s.rx_per_s, s.tx_per_s)).collect();
+        let net_max = net.iter().map(|(rx, tx)| rx + tx).max().unwrap_or(0).max(1024);
+        let net_title = alloc::format!(
+            "RX {} TX {}",
+            crate::perf::format_rate(latest.rx_per_s),
+            crate::perf::format_rate(latest.tx_per_s)
+        );
+        self.draw_task_manager_graph(net_rect, net_title.as_str(), net.as_slice(), net_max, (0x22C55E, 0xF59E0B));
+        self.task_manager_history = history;
+
+        // Process and job list
+        let list_rect = self.task_manager_list_rect();
+        let mut list = core::mem::take(&mut self.task_manager_list);
+        list.rect = list_rect;
+        list.draw(self, list_rect);
+        self.task_manager_list = list;
+
+        // Footer buttons
+        for (rect, _, color, label) in self.task_manager_buttons().iter() {
+            if rect.y + rect.height as i32 <= content_h_i32 {
+                self.fill_rect(*rect, Color(*color));
+                self.draw_border(*rect, Color(0x1F2937));
+                let text = Self::trim_label(label, 20);
+                self.draw_text((rect.x + 8) as u32, (rect.y + 6) as u32, text.as_bytes(), Color(0xF8FAFC));
+            }
+        }
+    }
+
+    pub fn render_about_pc(&mut self) {
+        if self.kind != WindowKind::AboutPc {
+            return;
+        }
+
+        let content_h = self.content_height();
+        if content_h <= 0 {
+            return;
+        }
+        let content_h_i32 = content_h as i32;
+
+        self.fill_rect(
+            Rect::new(0, 0, self.rect.width, content_h as u32),
+            Color(0x111827),
+        );
+        self.fill_rect(Rect::new(0, 0, self.rect.width, ABOUT_PC_HEADER_H as u32), Color(0x1F2937));
+        self.draw_text(14, 14, b"ACERCA DE ESTE EQUIPO", Color(0xF9FAFB));
+        self.draw_text(14, 28, b"Rueda del raton para desplazar", Color(0x9CA3AF));
+
+        let list_y = ABOUT_PC_HEADER_H + 6;
+        let list_h = (content_h_i32 - ABOUT_PC_HEADER_H - 12).max(40);
         self.fill_rect(
             Rect::new(10, list_y, self.rect.width.saturating_sub(20), list_h as u32),
             Color(0x0F172A),
@@ -5176,59 +7010,268 @@ Tab DOCS is read-only.\n"
             Color(0x334155),
         );
 
-        let visible_rows = (list_h / TASK_MGR_ROW_H).max(1) as usize;
-        let max_scroll = self.task_manager_lines.len().saturating_sub(visible_rows);
-        if self.task_manager_scroll > max_scroll {
-            self.task_manager_scroll = max_scroll;
+        let visible_rows = (list_h / ABOUT_PC_ROW_H).max(1) as usize;
+        let max_scroll = self.about_pc_lines.len().saturating_sub(visible_rows);
+        if self.about_pc_scroll > max_scroll {
+            self.about_pc_scroll = max_scroll;
         }
 
-        let start = self.task_manager_scroll;
-        let end = (start + visible_rows).min(self.task_manager_lines.len());
+        let max_chars = ((self.rect.width as i32 - 36) / 8).max(8) as usize;
+        let start = self.about_pc_scroll;
+        let end = (start + visible_rows).min(self.about_pc_lines.len());
         for idx in start..end {
-            let line = self.task_manager_lines[idx].clone();
-            let row = idx - start;
-            let row_y = list_y + 4 + (row as i32 * TASK_MGR_ROW_H);
-            if row_y + TASK_MGR_ROW_H > list_y + list_h {
+            let line = self.about_pc_lines[idx].clone();
+            let row_y = list_y + 4 + ((idx - start) as i32 * ABOUT_PC_ROW_H);
+            if row_y + ABOUT_PC_ROW_H > list_y + list_h {
                 break;
             }
-            if self.task_manager_selected == Some(idx) {
-                self.fill_rect(
-                    Rect::new(12, row_y - 2, self.rect.width.saturating_sub(24), TASK_MGR_ROW_H as u32),
-                    Color(0x1E40AF),
-                );
-            }
-            let text = Self::trim_label(line.as_str(), 72);
-            let color = if self.task_manager_selected == Some(idx) {
-                0xF8FAFC
-            } else {
-                0xE2E8F0
-            };
+            // Section headings are the only lines without leading indentation.
+            let color = if line.starts_with(' ') { 0xE2E8F0 } else { 0x93C5FD };
+            let text = Self::trim_label(line.as_str(), max_chars);
             self.draw_text(16, row_y as u32, text.as_bytes(), Color(color));
         }
+    }
 
-        // Footer buttons
-        let footer_y = content_h_i32 - TASK_MGR_FOOTER_H;
-        let btn_w = ((w - 30).max(140) / 2).max(120);
-        let btn_h = 22;
-        let left_x = 12;
-        let right_x = left_x + btn_w + 10;
-        let row1_y = footer_y + 8;
-        let row2_y = row1_y + btn_h + 8;
+    /// Mail text for the 5x7 font: accented letters lose the accent and any
+    /// other non-ASCII character becomes '?'.
+    fn mail_ascii(text: &str) -> String {
+        text.chars()
+            .map(|c| match c {
+                'á' | 'à' | 'ä' | 'â' | 'ã' => 'a',
+                'é' | 'è' | 'ë' | 'ê' => 'e',
+                'í' | 'ì' | 'ï' | 'î' => 'i',
+                'ó' | 'ò' | 'ö' | 'ô' | 'õ' => 'o',
+                'ú' | 'ù' | 'ü' | 'û' => 'u',
+                'Á' | 'À' | 'Ä' | 'Â' => 'A',
+                'É' | 'È' | 'Ë' => 'E',
+                'Í' | 'Ì' | 'Ï' => 'I',
+                'Ó' | 'Ò' | 'Ö' => 'O',
+                'Ú' | 'Ù' | 'Ü' => 'U',
+                'ñ' => 'n',
+                'Ñ' => 'N',
+                'ç' => 'c',
+                '\t' => ' ',
+                c if c.is_ascii() && !c.is_ascii_control() => c,
+                _ => '?',
+            })
+            .collect()
+    }
+
+    fn mail_toolbar_buttons(&self) -> &'static [(&'static str, MailClickAction)] {
+        match self.mail_view {
+            MailView::Inbox => &[
+                ("Actualizar", MailClickAction::Refresh),
+                ("Redactar", MailClickAction::Compose),
+                ("Responder", MailClickAction::Reply),
+                ("Cuenta", MailClickAction::Account),
+            ],
+            MailView::Compose => &[("Enviar", MailClickAction::Send), ("Cancelar", MailClickAction::Cancel)],
+            MailView::Account => &[("Guardar", MailClickAction::SaveAccount), ("Cancelar", MailClickAction::Cancel)],
+        }
+    }
+
+    fn mail_field_labels(&self) -> &'static [&'static str] {
+        match self.mail_view {
+            MailView::Inbox => &[],
+            MailView::Compose => &["Para:", "Asunto:", "Mensaje:"],
+            MailView::Account => &["Email:", "Clave:", "IMAP:", "SMTP:"],
+        }
+    }
+
+    fn mail_list_width(&self) -> i32 {
+        (self.rect.width as i32 * 2 / 5).clamp(200, 320)
+    }
+
+    /// Input box of form field `idx`. The compose body takes the rest of the
+    /// window.
+    fn mail_field_rect(&self, idx: usize) -> Rect {
+        let x = 96;
+        let y = MAIL_TOOLBAR_H + 10 + idx as i32 * MAIL_FIELD_SPACING;
+        let w = (self.rect.width as i32 - x - 12).max(80) as u32;
+        let h = if self.mail_view == MailView::Compose && idx == 2 {
+            (self.content_height() - MAIL_STATUS_H - 8 - y).max(MAIL_FIELD_H)
+        } else {
+            MAIL_FIELD_H
+        };
+        Rect::new(x, y, w, h as u32)
+    }
+
+    fn mail_body_visible_rows(&self) -> usize {
+        let h = self.content_height() - MAIL_TOOLBAR_H - MAIL_STATUS_H - 16 - 4 * MAIL_LINE_H;
+        (h / MAIL_LINE_H).max(1) as usize
+    }
+
+    fn mail_body_wrapped(&self) -> Vec<String> {
+        let cols = ((self.rect.width as i32 - self.mail_list_width() - 36) / 6).max(10) as usize;
+        let mut out = Vec::new();
+        for line in self.mail_body_lines.iter() {
+            Self::terminal_wrap_line_into(Self::mail_ascii(line.as_str()).as_str(), cols, &mut out);
+        }
+        out
+    }
+
+    pub fn render_mail(&mut self) {
+        if self.kind != WindowKind::Mail {
+            return;
+        }
+
+        let content_h = self.content_height();
+        if content_h <= 0 {
+            return;
+        }
+        let w = self.rect.width as i32;
+
+        self.fill_rect(Rect::new(0, 0, self.rect.width, content_h as u32), Color(0x111827));
+
+        // Toolbar
+        self.fill_rect(Rect::new(0, 0, self.rect.width, MAIL_TOOLBAR_H as u32), Color(0x1F2937));
+        for (i, (label, _)) in self.mail_toolbar_buttons().iter().enumerate() {
+            let bx = 10 + i as i32 * (MAIL_BUTTON_W + 8);
+            let color = if i == 0 { 0x1D4ED8 } else { 0x374151 };
+            self.fill_rect(Rect::new(bx, 6, MAIL_BUTTON_W as u32, 24), Color(color));
+            self.draw_border(Rect::new(bx, 6, MAIL_BUTTON_W as u32, 24), Color(0x4B5563));
+            self.draw_text((bx + 10) as u32, 15, label.as_bytes(), Color(0xF8FAFC));
+        }
+
+        match self.mail_view {
+            MailView::Inbox => self.render_mail_inbox(),
+            MailView::Compose | MailView::Account => self.render_mail_form(),
+        }
+
+        // Status bar
+        let status_y = content_h - MAIL_STATUS_H;
+        self.fill_rect(Rect::new(0, status_y, self.rect.width, MAIL_STATUS_H as u32), Color(0x1F2937));
+        let status = Self::trim_label(Self::mail_ascii(self.mail_status.as_str()).as_str(), ((w - 20) / 6).max(8) as usize);
+        self.draw_text(10, (status_y + 8) as u32, status.as_bytes(), Color(0x9CA3AF));
+    }
+
+    fn render_mail_inbox(&mut self) {
+        let content_h = self.content_height();
+        let list_w = self.mail_list_width();
+        let top = MAIL_TOOLBAR_H + 4;
+        let pane_h = (content_h - top - MAIL_STATUS_H - 4).max(20);
+
+        // Message list
+        self.fill_rect(Rect::new(6, top, (list_w - 6) as u32, pane_h as u32), Color(0x0F172A));
+        self.draw_border(Rect::new(6, top, (list_w - 6) as u32, pane_h as u32), Color(0x334155));
+        let visible_rows = (pane_h / MAIL_ROW_H).max(1) as usize;
+        let max_scroll = self.mail_entries.len().saturating_sub(visible_rows);
+        self.mail_list_scroll = self.mail_list_scroll.min(max_scroll);
+        let chars = ((list_w - 24) / 6).max(8) as usize;
+        let start = self.mail_list_scroll;
+        let end = (start + visible_rows).min(self.mail_entries.len());
+        for idx in start..end {
+            let row_y = top + 2 + (idx - start) as i32 * MAIL_ROW_H;
+            let selected = self.mail_selected == Some(idx);
+            if selected {
+                self.fill_rect(Rect::new(8, row_y, (list_w - 10) as u32, (MAIL_ROW_H - 2) as u32), Color(0x1E40AF));
+            }
+            let (from, subject, seen) = {
+                let entry = &self.mail_entries[idx];
+                (
+                    Self::trim_label(Self::mail_ascii(entry.from.as_str()).as_str(), chars),
+                    Self::trim_label(Self::mail_ascii(entry.subject.as_str()).as_str(), chars),
+                    entry.seen,
+                )
+            };
+            // Unread messages get a marker and brighter text.
+            if !seen {
+                self.fill_rect(Rect::new(10, row_y + 6, 4, 4), Color(0x60A5FA));
+            }
+            let from_color = if seen && !selected { 0x94A3B8 } else { 0xF8FAFC };
+            self.draw_text(18, (row_y + 4) as u32, from.as_bytes(), Color(from_color));
+            self.draw_text(18, (row_y + 16) as u32, subject.as_bytes(), Color(0xCBD5E1));
+        }
+        if self.mail_entries.is_empty() {
+            self.draw_text(16, (top + 10) as u32, b"(bandeja vacia)", Color(0x64748B));
+        }
 
-        let buttons = [
-            (left_x, row1_y, btn_w, btn_h, 0xB91C1C, "Cancelar Install"),
-            (right_x, row1_y, btn_w, btn_h, 0x9A3412, "Cancelar FS"),
-            (left_x, row2_y, btn_w, btn_h, 0x7C2D12, "Cancelar Paste"),
-            (right_x, row2_y, btn_w, btn_h, 0x991B1B, "Cancelar Todo"),
+        // Reading pane
+        let rx = list_w + 6;
+        let rw = (self.rect.width as i32 - rx - 6).max(40);
+        self.fill_rect(Rect::new(rx, top, rw as u32, pane_h as u32), Color(0x0F172A));
+        self.draw_border(Rect::new(rx, top, rw as u32, pane_h as u32), Color(0x334155));
+        let Some(entry) = self.mail_selected.and_then(|i| self.mail_entries.get(i)).cloned() else {
+            self.draw_text((rx + 10) as u32, (top + 10) as u32, b"Selecciona un mensaje.", Color(0x64748B));
+            return;
+        };
+        let cols = ((rw - 20) / 6).max(8) as usize;
+        let header = [
+            ("De: ", entry.from.as_str()),
+            ("Fecha: ", entry.date.as_str()),
+            ("Asunto: ", entry.subject.as_str()),
         ];
+        for (i, (label, value)) in header.iter().enumerate() {
+            let line = alloc::format!("{}{}", label, Self::mail_ascii(value));
+            let line = Self::trim_label(line.as_str(), cols);
+            let color = if i == 2 { 0xF9FAFB } else { 0x93C5FD };
+            self.draw_text((rx + 10) as u32, (top + 8 + i as i32 * MAIL_LINE_H) as u32, line.as_bytes(), Color(color));
+        }
+        let sep_y = top + 8 + 3 * MAIL_LINE_H;
+        self.fill_rect(Rect::new(rx + 6, sep_y, (rw - 12).max(0) as u32, 1), Color(0x334155));
+
+        let lines = self.mail_body_wrapped();
+        let visible = self.mail_body_visible_rows();
+        self.mail_body_scroll = self.mail_body_scroll.min(lines.len().saturating_sub(visible));
+        let body_top = sep_y + 6;
+        for (i, line) in lines.iter().skip(self.mail_body_scroll).take(visible).enumerate() {
+            let y = body_top + i as i32 * MAIL_LINE_H;
+            if y + MAIL_LINE_H > top + pane_h {
+                break;
+            }
+            let color = if line.starts_with('>') { 0x86EFAC } else { 0xE2E8F0 };
+            self.draw_text((rx + 10) as u32, y as u32, line.as_bytes(), Color(color));
+        }
+    }
+
+    fn render_mail_form(&mut self) {
+        let labels = self.mail_field_labels();
+        for (idx, label) in labels.iter().enumerate() {
+            let rect = self.mail_field_rect(idx);
+            let focused = self.mail_focus == idx;
+            self.draw_text(14, (rect.y + 8) as u32, label.as_bytes(), Color(0xCBD5E1));
+            self.fill_rect(rect, Color(0x0F172A));
+            self.draw_border(rect, Color(if focused { 0x3B82F6 } else { 0x334155 }));
+
+            let value = self.mail_fields.get(idx).cloned().unwrap_or_default();
+            let cols = ((rect.width as i32 - 12) / 6).max(4) as usize;
+            let is_body = self.mail_view == MailView::Compose && idx == 2;
+            if is_body {
+                // Keep the end of the text, where typing happens, in view.
+                let rows = ((rect.height as i32 - 8) / MAIL_LINE_H).max(1) as usize;
+                let mut wrapped = Vec::new();
+                for line in Self::mail_ascii(value.as_str()).split('\n') {
+                    Self::terminal_wrap_line_into(line, cols, &mut wrapped);
+                }
+                if focused {
+                    if let Some(last) = wrapped.last_mut() {
+                        last.push('_');
+                    }
+                }
+                let skip = wrapped.len().saturating_sub(rows);
+                for (i, line) in wrapped.iter().skip(skip).enumerate() {
+                    let y = rect.y + 5 + i as i32 * MAIL_LINE_H;
+                    self.draw_text((rect.x + 6) as u32, y as u32, line.as_bytes(), Color(0xF1F5F9));
+                }
+                continue;
+            }
 
-        for (bx, by, bw, bh, color, label) in buttons.iter() {
-            if *by + *bh <= content_h_i32 {
-                self.fill_rect(Rect::new(*bx, *by, *bw as u32, *bh as u32), Color(*color));
-                self.draw_border(Rect::new(*bx, *by, *bw as u32, *bh as u32), Color(0x1F2937));
-                let text = Self::trim_label(label, 20);
-                self.draw_text((*bx + 8) as u32, (*by + 6) as u32, text.as_bytes(), Color(0xF8FAFC));
+            let mut shown = if self.mail_view == MailView::Account && idx == 1 {
+                "*".repeat(value.chars().count())
+            } else {
+                Self::mail_ascii(value.as_str())
+            };
+            if focused {
+                shown.push('_');
             }
+            // Show the tail of long values, like a scrolled text box.
+            let skip = shown.len().saturating_sub(cols);
+            self.draw_text((rect.x + 6) as u32, (rect.y + 8) as u32, shown[skip..].as_bytes(), Color(0xF1F5F9));
+        }
+        if self.mail_view == MailView::Account {
+            let y = self.mail_field_rect(labels.len()).y + 4;
+            self.draw_text(14, y as u32, b"Servidores como host o host:puerto; vacios = imap./smtp.<dominio>.", Color(0x64748B));
+            self.draw_text(14, (y + MAIL_LINE_H) as u32, b"La cuenta se guarda en MAIL/ACCOUNT.CFG del volumen activo.", Color(0x64748B));
         }
     }
 
@@ -5976,6 +8019,66 @@ Tab DOCS is read-only.\n"
         self.render();
     }
 
+    /// Show `document` starting at `page` (from 0), fitted to the width.
+    pub fn load_pdf_viewer(&mut self, file_name: &str, document: crate::pdf::Document, page: usize) {
+        if self.kind != WindowKind::PdfViewer {
+            return;
+        }
+        self.pdf_viewer_file_name = String::from(file_name);
+        self.pdf_viewer_page = page.min(document.page_count().saturating_sub(1));
+        self.pdf_viewer_document = Some(document);
+        self.pdf_viewer_fit_width = true;
+        self.pdf_viewer_scroll_x = 0;
+        self.pdf_viewer_scroll_y = 0;
+        self.pdf_viewer_render_page();
+        self.render();
+    }
+
+    /// Toolbar buttons and their keys (arrows, +/-). Returns whether
+    /// anything changed.
+    pub fn pdf_viewer_apply(&mut self, action: PdfViewerClickAction) -> bool {
+        let Some(count) = self.pdf_viewer_document.as_ref().map(crate::pdf::Document::page_count) else {
+            return false;
+        };
+        match action {
+            PdfViewerClickAction::Prev | PdfViewerClickAction::Next => {
+                let page = if action == PdfViewerClickAction::Prev {
+                    self.pdf_viewer_page.checked_sub(1)
+                } else {
+                    Some(self.pdf_viewer_page + 1).filter(|p| *p < count)
+                };
+                let Some(page) = page else {
+                    return false;
+                };
+                self.pdf_viewer_page = page;
+                self.pdf_viewer_scroll_y = 0;
+            }
+            PdfViewerClickAction::ZoomOut | PdfViewerClickAction::ZoomIn => {
+                let zoom = self.pdf_viewer_zoom;
+                let next = if action == PdfViewerClickAction::ZoomIn {
+                    PDF_VIEWER_ZOOMS.iter().copied().find(|z| *z > zoom)
+                } else {
+                    PDF_VIEWER_ZOOMS.iter().rev().copied().find(|z| *z < zoom)
+                };
+                let Some(next) = next else {
+                    return false;
+                };
+                self.pdf_viewer_fit_width = false;
+                self.pdf_viewer_zoom = next;
+            }
+            PdfViewerClickAction::Fit => {
+                if self.pdf_viewer_fit_width {
+                    return false;
+                }
+                self.pdf_viewer_fit_width = true;
+                self.pdf_viewer_scroll_x = 0;
+            }
+        }
+        self.pdf_viewer_render_page();
+        self.render();
+        true
+    }
+
     pub fn load_app_runner_layout(
         &mut self,
         source_file: &str,
@@ -7062,7 +9165,7 @@ Tab DOCS is read-only.\n"
         self.notepad_dir_cluster = dir_cluster;
         self.notepad_dir_path = String::from(dir_path);
         self.notepad_file_name = String::from(file_name);
-        self.notepad_text = String::from(text);
+        self.notepad_area.set_text(text);
         self.notepad_status = String::from(status);
         self.notepad_edit_name = false;
         self.render();
@@ -7082,18 +9185,74 @@ Tab DOCS is read-only.\n"
         }
 
         self.notepad_file_name = String::from(default_name);
-        self.notepad_text.clear();
+        self.notepad_area.clear();
         self.notepad_edit_name = true;
         self.notepad_status = String::from("New file. Type a name and edit text.");
         self.render();
     }
 
-    pub fn set_notepad_filename_focus(&mut self, focus: bool) {
+    pub fn set_notepad_filename_focus(&mut self, focus: bool) {
+        if self.kind != WindowKind::Notepad {
+            return;
+        }
+        self.notepad_edit_name = focus;
+        self.render();
+    }
+
+    pub fn notepad_move_cursor(&mut self, special: SpecialKey) -> bool {
+        if self.kind != WindowKind::Notepad || self.notepad_edit_name {
+            return false;
+        }
+        let changed = self.notepad_area_mut().move_cursor(special);
+        if changed {
+            self.render();
+        }
+        changed
+    }
+
+    /// Place the caret under the pointer and start a drag selection.
+    pub fn notepad_press_at(&mut self, global_x: i32, global_y: i32) {
+        if self.kind != WindowKind::Notepad {
+            return;
+        }
+        let p = crate::gui::Point {
+            x: global_x - self.rect.x,
+            y: global_y - (self.rect.y + TITLE_BAR_H),
+        };
+        self.notepad_edit_name = false;
+        self.notepad_area_mut().press(p);
+        self.render();
+    }
+
+    pub fn notepad_drag_to(&mut self, global_x: i32, global_y: i32) -> bool {
+        if self.kind != WindowKind::Notepad {
+            return false;
+        }
+        let p = crate::gui::Point {
+            x: global_x - self.rect.x,
+            y: global_y - (self.rect.y + TITLE_BAR_H),
+        };
+        let changed = self.notepad_area_mut().drag(p);
+        if changed {
+            self.render();
+        }
+        changed
+    }
+
+    pub fn notepad_release_drag(&mut self) {
+        self.notepad_area.release();
+    }
+
+    /// Positive `delta_rows` scrolls up, like the wheel.
+    pub fn notepad_scroll_by(&mut self, delta_rows: i32) -> bool {
         if self.kind != WindowKind::Notepad {
-            return;
+            return false;
         }
-        self.notepad_edit_name = focus;
-        self.render();
+        let changed = self.notepad_area_mut().scroll_by(-delta_rows * 3);
+        if changed {
+            self.render();
+        }
+        changed
     }
 
     pub fn notepad_action_at(&self, global_x: i32, global_y: i32) -> Option<NotepadClickAction> {
@@ -7116,20 +9275,28 @@ Tab DOCS is read-only.\n"
         };
 
         let new_btn = self.notepad_button_rect(0);
-        let save_btn = self.notepad_button_rect(1);
-        let delete_btn = self.notepad_button_rect(2);
+        let open_btn = self.notepad_button_rect(1);
+        let save_btn = self.notepad_button_rect(2);
+        let delete_btn = self.notepad_button_rect(3);
+        let print_btn = self.notepad_button_rect(4);
         let name_rect = self.notepad_filename_rect();
         let editor_rect = self.notepad_editor_rect();
 
         if new_btn.contains(p) {
             return Some(NotepadClickAction::New);
         }
+        if open_btn.contains(p) {
+            return Some(NotepadClickAction::Open);
+        }
         if save_btn.contains(p) {
             return Some(NotepadClickAction::Save);
         }
         if delete_btn.contains(p) {
             return Some(NotepadClickAction::Delete);
         }
+        if print_btn.contains(p) {
+            return Some(NotepadClickAction::Print);
+        }
         if name_rect.contains(p) {
             return Some(NotepadClickAction::FilenameField);
         }
@@ -7232,61 +9399,190 @@ Tab DOCS is read-only.\n"
             return None;
         }
 
-        let content_h = self.content_height();
-        if content_h <= 0 {
+        let p = crate::gui::Point {
+            x: local_x,
+            y: local_y,
+        };
+
+        for (rect, action, _, _) in self.task_manager_buttons().iter() {
+            if rect.contains(p) {
+                return Some(*action);
+            }
+        }
+
+        if !self.task_manager_list.rect.contains(p) {
+            return None;
+        }
+        if let Some(column) = self.task_manager_list.column_at(p) {
+            return Self::task_manager_sort_key(column).map(TaskManagerClickAction::Sort);
+        }
+        self.task_manager_list.row_at(p).map(TaskManagerClickAction::Select)
+    }
+
+    pub fn boot_entries_action_at(&self, global_x: i32, global_y: i32) -> Option<BootEntriesClickAction> {
+        if self.kind != WindowKind::BootEntries {
+            return None;
+        }
+
+        let local_x = global_x - self.rect.x;
+        let local_y = global_y - (self.rect.y + TITLE_BAR_H);
+        if local_x < 0 || local_y < 0 {
+            return None;
+        }
+        if local_x >= self.rect.width as i32 || local_y >= self.content_height() {
             return None;
         }
 
-        let w = self.rect.width as i32;
-        let content_h_i32 = content_h as i32;
         let p = crate::gui::Point {
             x: local_x,
             y: local_y,
         };
 
-        // Footer buttons (match render_task_manager layout)
-        let footer_y = content_h_i32 - TASK_MGR_FOOTER_H;
-        let btn_w = ((w - 30).max(140) / 2).max(120);
-        let btn_h = 22;
-        let left_x = 12;
-        let right_x = left_x + btn_w + 10;
-        let row1_y = footer_y + 8;
-        let row2_y = row1_y + btn_h + 8;
+        for (rect, action, _, _) in self.boot_entries_buttons().iter() {
+            if rect.contains(p) {
+                return Some(*action);
+            }
+        }
 
-        let cancel_install = Rect::new(left_x, row1_y, btn_w as u32, btn_h as u32);
-        if cancel_install.contains(p) {
-            return Some(TaskManagerClickAction::CancelInstall);
-        }
-        let cancel_fs = Rect::new(right_x, row1_y, btn_w as u32, btn_h as u32);
-        if cancel_fs.contains(p) {
-            return Some(TaskManagerClickAction::CancelFs);
-        }
-        let cancel_paste = Rect::new(left_x, row2_y, btn_w as u32, btn_h as u32);
-        if cancel_paste.contains(p) {
-            return Some(TaskManagerClickAction::CancelPaste);
-        }
-        let cancel_all = Rect::new(right_x, row2_y, btn_w as u32, btn_h as u32);
-        if cancel_all.contains(p) {
-            return Some(TaskManagerClickAction::CancelAll);
-        }
-
-        // List selection
-        let list_y = TASK_MGR_HEADER_H + 6;
-        let list_h = (content_h_i32 - TASK_MGR_HEADER_H - TASK_MGR_FOOTER_H).max(60);
-        if local_y >= list_y && local_y < list_y + list_h {
-            let row_offset = local_y - (list_y + 4);
-            if row_offset >= 0 {
-                let row = (row_offset / TASK_MGR_ROW_H) as usize;
-                let visible_rows = (list_h / TASK_MGR_ROW_H).max(1) as usize;
-                if row < visible_rows {
-                    let idx = self.task_manager_scroll + row;
-                    if idx < self.task_manager_lines.len() {
-                        return Some(TaskManagerClickAction::Select(idx));
-                    }
+        if !self.boot_entries_list.rect.contains(p) {
+            return None;
+        }
+        self.boot_entries_list.row_at(p).map(BootEntriesClickAction::Select)
+    }
+
+    pub fn pdf_viewer_action_at(&self, global_x: i32, global_y: i32) -> Option<PdfViewerClickAction> {
+        if self.kind != WindowKind::PdfViewer {
+            return None;
+        }
+        let local_x = global_x - self.rect.x;
+        let local_y = global_y - (self.rect.y + TITLE_BAR_H);
+        if local_x < 0 || local_y < 0 || local_x >= self.rect.width as i32 || local_y >= self.content_height() {
+            return None;
+        }
+        let p = crate::gui::Point { x: local_x, y: local_y };
+        self.pdf_viewer_buttons()
+            .iter()
+            .find(|(rect, _, _)| rect.contains(p))
+            .map(|(_, action, _)| *action)
+    }
+
+    /// `KEYPAD` label of the calculator key under the pointer.
+    pub fn calculator_key_at(&self, global_x: i32, global_y: i32) -> Option<&'static str> {
+        if self.kind != WindowKind::Calculator {
+            return None;
+        }
+        let local_x = global_x - self.rect.x;
+        let local_y = global_y - (self.rect.y + TITLE_BAR_H);
+        if local_x < 0 || local_y < 0 || local_x >= self.rect.width as i32 || local_y >= self.content_height() {
+            return None;
+        }
+        let p = crate::gui::Point { x: local_x, y: local_y };
+        self.calculator_buttons()
+            .iter()
+            .position(|button| button.rect.contains(p))
+            .map(|i| crate::calc::KEYPAD[i])
+    }
+
+    pub fn calendar_action_at(&self, global_x: i32, global_y: i32) -> Option<CalendarClickAction> {
+        if self.kind != WindowKind::Calendar {
+            return None;
+        }
+        let local_x = global_x - self.rect.x;
+        let local_y = global_y - (self.rect.y + TITLE_BAR_H);
+        if local_x < 0 || local_y < 0 || local_x >= self.rect.width as i32 || local_y >= self.content_height() {
+            return None;
+        }
+        let p = crate::gui::Point { x: local_x, y: local_y };
+        if let Some((_, action)) = self.calendar_buttons().iter().find(|(button, _)| button.rect.contains(p)) {
+            return Some(*action);
+        }
+        let date = self.calendar_selected;
+        let grid = crate::calendar::month_grid(date.year, date.month);
+        for (row, week) in grid.iter().enumerate() {
+            for (col, day) in week.iter().enumerate() {
+                if *day != 0 && self.calendar_cell_rect(row, col).contains(p) {
+                    return Some(CalendarClickAction::Day(*day));
                 }
             }
         }
+        self.calendar_events.row_at(p).map(CalendarClickAction::Event)
+    }
+
+    pub fn clock_action_at(&self, global_x: i32, global_y: i32) -> Option<ClockClickAction> {
+        if self.kind != WindowKind::Clock {
+            return None;
+        }
+        let local_x = global_x - self.rect.x;
+        let local_y = global_y - (self.rect.y + TITLE_BAR_H);
+        if local_x < 0 || local_y < 0 || local_x >= self.rect.width as i32 || local_y >= self.content_height() {
+            return None;
+        }
+        let p = crate::gui::Point { x: local_x, y: local_y };
+        if let Some((_, action)) = self.clock_buttons().iter().find(|(button, _)| button.rect.contains(p)) {
+            return Some(*action);
+        }
+        self.clock_list.row_at(p).map(ClockClickAction::Select)
+    }
+
+    pub fn recorder_action_at(&self, global_x: i32, global_y: i32) -> Option<RecorderClickAction> {
+        if self.kind != WindowKind::Recorder {
+            return None;
+        }
+        let p = crate::gui::Point {
+            x: global_x - self.rect.x,
+            y: global_y - (self.rect.y + TITLE_BAR_H),
+        };
+        self.recorder_buttons()
+            .iter()
+            .find(|(button, _)| button.rect.contains(p))
+            .map(|(_, action)| *action)
+    }
+
+    pub fn mail_action_at(&self, global_x: i32, global_y: i32) -> Option<MailClickAction> {
+        if self.kind != WindowKind::Mail {
+            return None;
+        }
+
+        let local_x = global_x - self.rect.x;
+        let local_y = global_y - (self.rect.y + TITLE_BAR_H);
+        let content_h = self.content_height();
+        if local_x < 0 || local_y < 0 || local_x >= self.rect.width as i32 || local_y >= content_h {
+            return None;
+        }
+        let p = crate::gui::Point { x: local_x, y: local_y };
+
+        // Toolbar (match render_mail layout)
+        for (i, (_, action)) in self.mail_toolbar_buttons().iter().enumerate() {
+            let bx = 10 + i as i32 * (MAIL_BUTTON_W + 8);
+            if Rect::new(bx, 6, MAIL_BUTTON_W as u32, 24).contains(p) {
+                return Some(*action);
+            }
+        }
 
+        match self.mail_view {
+            MailView::Inbox => {
+                let top = MAIL_TOOLBAR_H + 4;
+                let pane_h = (content_h - top - MAIL_STATUS_H - 4).max(20);
+                if local_x >= self.mail_list_width() || local_y < top + 2 || local_y >= top + pane_h {
+                    return None;
+                }
+                let row = ((local_y - top - 2) / MAIL_ROW_H) as usize;
+                if row >= (pane_h / MAIL_ROW_H).max(1) as usize {
+                    return None;
+                }
+                let idx = self.mail_list_scroll + row;
+                if idx < self.mail_entries.len() {
+                    return Some(MailClickAction::Select(idx));
+                }
+            }
+            MailView::Compose | MailView::Account => {
+                for idx in 0..self.mail_field_labels().len() {
+                    if self.mail_field_rect(idx).contains(p) {
+                        return Some(MailClickAction::Field(idx));
+                    }
+                }
+            }
+        }
         None
     }
 
@@ -7337,6 +9633,26 @@ Tab DOCS is read-only.\n"
             .contains(crate::gui::Point { x: local_x, y: local_y })
     }
 
+    pub fn browser_download_clicked(&self, global_x: i32, global_y: i32) -> bool {
+        if self.kind != WindowKind::Browser {
+            return false;
+        }
+        let local_x = global_x - self.rect.x;
+        let local_y = global_y - (self.rect.y + TITLE_BAR_H);
+        self.browser_download_rect()
+            .contains(crate::gui::Point { x: local_x, y: local_y })
+    }
+
+    pub fn browser_print_clicked(&self, global_x: i32, global_y: i32) -> bool {
+        if self.kind != WindowKind::Browser {
+            return false;
+        }
+        let local_x = global_x - self.rect.x;
+        let local_y = global_y - (self.rect.y + TITLE_BAR_H);
+        self.browser_print_rect()
+            .contains(crate::gui::Point { x: local_x, y: local_y })
+    }
+
     pub fn browser_back_clicked(&self, global_x: i32, global_y: i32) -> bool {
         if self.kind != WindowKind::Browser {
             return false;
@@ -7361,6 +9677,36 @@ Tab DOCS is read-only.\n"
         })
     }
 
+    /// Where the browser surface is drawn, in content coordinates: the
+    /// viewport minus a 4px margin, aspect preserved, centered.
+    pub fn browser_surface_draw_rect(&self) -> Option<Rect> {
+        if self.kind != WindowKind::Browser {
+            return None;
+        }
+        let surf_w = self.browser_surface_width as usize;
+        let surf_h = self.browser_surface_height as usize;
+        if surf_w == 0 || surf_h == 0 || self.browser_surface_pixels.len() < surf_w.saturating_mul(surf_h) {
+            return None;
+        }
+
+        let view_rect = self.browser_viewport_rect();
+        let avail_w = (view_rect.width as i32 - 8).max(1) as usize;
+        let avail_h = (view_rect.height as i32 - 8).max(1) as usize;
+
+        let mut draw_w = avail_w;
+        let mut draw_h = (surf_h.saturating_mul(draw_w)).max(1) / surf_w.max(1);
+        if draw_h > avail_h {
+            draw_h = avail_h;
+            draw_w = (surf_w.saturating_mul(draw_h)).max(1) / surf_h.max(1);
+        }
+        draw_w = draw_w.max(1).min(avail_w);
+        draw_h = draw_h.max(1).min(avail_h);
+
+        let start_x = view_rect.x + ((view_rect.width as i32 - draw_w as i32) / 2);
+        let start_y = view_rect.y + ((view_rect.height as i32 - draw_h as i32) / 2);
+        Some(Rect::new(start_x, start_y, draw_w as u32, draw_h as u32))
+    }
+
     pub fn browser_surface_point_at(
         &self,
         global_x: i32,
@@ -7389,27 +9735,9 @@ Tab DOCS is read-only.\n"
 
         let surf_w = self.browser_surface_width as usize;
         let surf_h = self.browser_surface_height as usize;
-        if surf_w == 0 || surf_h == 0 {
-            return None;
-        }
-        if self.browser_surface_pixels.len() < surf_w.saturating_mul(surf_h) {
-            return None;
-        }
-
-        let avail_w = (view_rect.width as i32 - 8).max(1) as usize;
-        let avail_h = (view_rect.height as i32 - 8).max(1) as usize;
-
-        let mut draw_w = avail_w;
-        let mut draw_h = (surf_h.saturating_mul(draw_w)).max(1) / surf_w.max(1);
-        if draw_h > avail_h {
-            draw_h = avail_h;
-            draw_w = (surf_w.saturating_mul(draw_h)).max(1) / surf_h.max(1);
-        }
-        draw_w = draw_w.max(1).min(avail_w);
-        draw_h = draw_h.max(1).min(avail_h);
-
-        let start_x = view_rect.x + ((view_rect.width as i32 - draw_w as i32) / 2);
-        let start_y = view_rect.y + ((view_rect.height as i32 - draw_h as i32) / 2);
+        let draw = self.browser_surface_draw_rect()?;
+        let (start_x, start_y) = (draw.x, draw.y);
+        let (draw_w, draw_h) = (draw.width as usize, draw.height as usize);
 
         let rel_x = local_x - start_x;
         let rel_y = local_y - start_y;
@@ -7464,6 +9792,7 @@ Tab DOCS is read-only.\n"
             WindowKind::Terminal => {
                 if ch.is_ascii() && !ch.is_control() {
                     self.input_buffer.push(ch);
+                    crate::gui::caret::reset();
                     self.render();
                 }
             }
@@ -7480,8 +9809,7 @@ Tab DOCS is read-only.\n"
                         self.notepad_file_name.push(ch.to_ascii_uppercase());
                         self.render();
                     }
-                } else {
-                    self.notepad_text.push(ch);
+                } else if self.notepad_area_mut().key(Some(ch), None) {
                     self.render();
                 }
             }
@@ -7545,6 +9873,26 @@ Tab DOCS is read-only.\n"
                 }
             }
             WindowKind::TaskManager => {}
+            WindowKind::AboutPc => {}
+            WindowKind::BootEntries => {}
+            WindowKind::PdfViewer => {}
+            WindowKind::Calculator | WindowKind::Calendar | WindowKind::Clock | WindowKind::Recorder => {}
+            WindowKind::Mail => {
+                if self.mail_view == MailView::Inbox {
+                    return;
+                }
+                if ch.is_control() {
+                    return;
+                }
+                let is_body = self.mail_view == MailView::Compose && self.mail_focus == 2;
+                let max_len = if is_body { MAIL_BODY_MAX_LEN } else { MAIL_FIELD_MAX_LEN };
+                if let Some(field) = self.mail_fields.get_mut(self.mail_focus) {
+                    if field.len() < max_len {
+                        field.push(ch);
+                        self.render();
+                    }
+                }
+            }
         }
     }
 
@@ -7553,6 +9901,7 @@ Tab DOCS is read-only.\n"
             WindowKind::Terminal => {
                 if !self.input_buffer.is_empty() {
                     self.input_buffer.pop();
+                    crate::gui::caret::reset();
                     self.render();
                 }
             }
@@ -7562,8 +9911,7 @@ Tab DOCS is read-only.\n"
                         self.notepad_file_name.pop();
                         self.render();
                     }
-                } else if !self.notepad_text.is_empty() {
-                    self.notepad_text.pop();
+                } else if self.notepad_area_mut().backspace() {
                     self.render();
                 }
             }
@@ -7634,6 +9982,20 @@ Tab DOCS is read-only.\n"
                 }
             }
             WindowKind::TaskManager => {}
+            WindowKind::AboutPc => {}
+            WindowKind::BootEntries => {}
+            WindowKind::PdfViewer => {}
+            WindowKind::Calculator | WindowKind::Calendar | WindowKind::Clock | WindowKind::Recorder => {}
+            WindowKind::Mail => {
+                if self.mail_view == MailView::Inbox {
+                    return;
+                }
+                if let Some(field) = self.mail_fields.get_mut(self.mail_focus) {
+                    if field.pop().is_some() {
+                        self.render();
+                    }
+                }
+            }
         }
     }
 
@@ -7657,7 +10019,7 @@ Tab DOCS is read-only.\n"
                     self.notepad_edit_name = false;
                     self.notepad_status = String::from("Filename set.");
                 } else {
-                    self.notepad_text.push('\n');
+                    self.notepad_area_mut().insert_str("\n");
                 }
                 self.render();
                 None
@@ -7705,6 +10067,24 @@ Tab DOCS is read-only.\n"
             WindowKind::VideoPlayer => None,
             WindowKind::WifiManager => None,
             WindowKind::TaskManager => None,
+            WindowKind::AboutPc => None,
+            WindowKind::BootEntries => None,
+            WindowKind::PdfViewer => None,
+            WindowKind::Calculator | WindowKind::Calendar | WindowKind::Clock | WindowKind::Recorder => None,
+            WindowKind::Mail => {
+                // New line in the message body; elsewhere, next field.
+                if self.mail_view == MailView::Compose && self.mail_focus == 2 {
+                    if let Some(body) = self.mail_fields.get_mut(2) {
+                        if body.len() < MAIL_BODY_MAX_LEN {
+                            body.push('\n');
+                        }
+                    }
+                } else if self.mail_view != MailView::Inbox {
+                    self.mail_focus = (self.mail_focus + 1) % self.mail_field_labels().len().max(1);
+                }
+                self.render();
+                None
+            }
         }
     }
 
@@ -7756,31 +10136,144 @@ Tab DOCS is read-only.\n"
         if self.kind != WindowKind::TaskManager || delta_rows == 0 {
             return false;
         }
+        self.task_manager_list.rect = self.task_manager_list_rect();
+        if !self.task_manager_list.scroll_by(delta_rows) {
+            return false;
+        }
+        self.render();
+        true
+    }
+
+    pub fn boot_entries_scroll_by(&mut self, delta_rows: i32) -> bool {
+        if self.kind != WindowKind::BootEntries || delta_rows == 0 {
+            return false;
+        }
+        self.boot_entries_list.rect = self.boot_entries_list_rect();
+        if !self.boot_entries_list.scroll_by(delta_rows) {
+            return false;
+        }
+        self.render();
+        true
+    }
+
+    /// Scroll the page; past the bottom or top edge, turn to the next or
+    /// previous page.
+    pub fn pdf_viewer_scroll_by(&mut self, delta_rows: i32) -> bool {
+        if self.kind != WindowKind::PdfViewer || delta_rows == 0 || self.pdf_viewer_rendered.is_none() {
+            return false;
+        }
+        let (_, max_y) = self.pdf_viewer_scroll_limits();
+        let before = self.pdf_viewer_scroll_y;
+        if delta_rows > 0 && before >= max_y {
+            return self.pdf_viewer_apply(PdfViewerClickAction::Next);
+        }
+        if delta_rows < 0 && before == 0 {
+            if !self.pdf_viewer_apply(PdfViewerClickAction::Prev) {
+                return false;
+            }
+            self.pdf_viewer_scroll_y = self.pdf_viewer_scroll_limits().1;
+            self.render();
+            return true;
+        }
+        self.pdf_viewer_scroll_y = (before + delta_rows * PDF_VIEWER_SCROLL_STEP).clamp(0, max_y);
+        self.render();
+        true
+    }
+
+    /// Wheel over the calculator history, the day's events or the alarm list.
+    pub fn applet_scroll_by(&mut self, delta_rows: i32) -> bool {
+        if delta_rows == 0 {
+            return false;
+        }
+        let moved = match self.kind {
+            WindowKind::Calculator => {
+                self.calculator_history.rect = self.calculator_history_rect();
+                self.calculator_history.scroll_by(delta_rows)
+            }
+            WindowKind::Calendar => {
+                self.calendar_events.rect = self.calendar_events_rect();
+                self.calendar_events.scroll_by(delta_rows)
+            }
+            WindowKind::Clock => {
+                self.clock_list.rect = self.clock_list_rect();
+                self.clock_list.scroll_by(delta_rows)
+            }
+            _ => false,
+        };
+        if moved {
+            self.render();
+        }
+        moved
+    }
+
+    pub fn about_pc_scroll_by(&mut self, delta_rows: i32) -> bool {
+        if self.kind != WindowKind::AboutPc || delta_rows == 0 {
+            return false;
+        }
         let content_h = self.content_height();
         if content_h <= 0 {
             return false;
         }
-        let list_h = (content_h as i32 - TASK_MGR_HEADER_H - TASK_MGR_FOOTER_H).max(60);
-        let visible_rows = (list_h / TASK_MGR_ROW_H).max(1) as usize;
-        let max_scroll = self.task_manager_lines.len().saturating_sub(visible_rows);
-        if max_scroll == 0 {
-            self.task_manager_scroll = 0;
+        let list_h = (content_h as i32 - ABOUT_PC_HEADER_H - 12).max(40);
+        let visible_rows = (list_h / ABOUT_PC_ROW_H).max(1) as usize;
+        let max_scroll = self.about_pc_lines.len().saturating_sub(visible_rows);
+        let rows = (delta_rows.unsigned_abs() as usize).max(1);
+        let before = self.about_pc_scroll;
+        if delta_rows > 0 {
+            self.about_pc_scroll = self.about_pc_scroll.saturating_add(rows).min(max_scroll);
+        } else {
+            self.about_pc_scroll = self.about_pc_scroll.saturating_sub(rows);
+        }
+        if self.about_pc_scroll == before {
+            return false;
+        }
+        self.render();
+        true
+    }
+
+    /// Up/Down keys: previous/next form field, or scroll the open message
+    /// in the inbox.
+    pub fn mail_move_focus(&mut self, delta: i32) -> bool {
+        if self.kind != WindowKind::Mail || delta == 0 {
             return false;
         }
+        if self.mail_view == MailView::Inbox {
+            let reading_x = self.rect.x + self.mail_list_width() + 1;
+            return self.mail_scroll_at(reading_x, delta * 3);
+        }
+        let count = self.mail_field_labels().len().max(1);
+        self.mail_focus = if delta > 0 {
+            (self.mail_focus + 1) % count
+        } else {
+            (self.mail_focus + count - 1) % count
+        };
+        self.render();
+        true
+    }
 
-        let magnitude = if delta_rows < 0 {
-            delta_rows.saturating_neg() as usize
+    /// Wheel over the Mail window: scrolls the message list or the reading
+    /// pane, whichever is under the pointer.
+    pub fn mail_scroll_at(&mut self, global_x: i32, delta_rows: i32) -> bool {
+        if self.kind != WindowKind::Mail || self.mail_view != MailView::Inbox || delta_rows == 0 {
+            return false;
+        }
+        let rows = (delta_rows.unsigned_abs() as usize).max(1);
+        let in_list = global_x - self.rect.x < self.mail_list_width();
+        let (scroll, max_scroll) = if in_list {
+            let pane_h = (self.content_height() - MAIL_TOOLBAR_H - 8 - MAIL_STATUS_H).max(20);
+            let visible = (pane_h / MAIL_ROW_H).max(1) as usize;
+            (&mut self.mail_list_scroll, self.mail_entries.len().saturating_sub(visible))
         } else {
-            delta_rows as usize
+            let max = self.mail_body_wrapped().len().saturating_sub(self.mail_body_visible_rows());
+            (&mut self.mail_body_scroll, max)
         };
-        let rows = magnitude.max(1);
-        let before = self.task_manager_scroll;
+        let before = *scroll;
         if delta_rows > 0 {
-            self.task_manager_scroll = self.task_manager_scroll.saturating_add(rows).min(max_scroll);
+            *scroll = scroll.saturating_add(rows).min(max_scroll);
         } else {
-            self.task_manager_scroll = self.task_manager_scroll.saturating_sub(rows);
+            *scroll = scroll.saturating_sub(rows);
         }
-        if self.task_manager_scroll == before {
+        if *scroll == before {
             return false;
         }
         self.render();
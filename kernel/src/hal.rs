@@ -174,3 +174,18 @@ pub fn pause() {
     #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
     unsupported("pause");
 }
+
+#[inline]
+pub fn rdtsc() -> u64 {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        let lo: u32;
+        let hi: u32;
+        unsafe {
+            asm!("rdtsc", out("eax") lo, out("edx") hi, options(nomem, nostack, preserves_flags));
+        }
+        ((hi as u64) << 32) | lo as u64
+    }
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    unsupported("rdtsc")
+}
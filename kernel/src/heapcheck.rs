@@ -0,0 +1,576 @@
+//! Heap canaries and use-after-free checks for debug builds.
+//!
+//! Built with the `heapcheck` feature (`make KERNEL_FEATURES=heapcheck`),
+//! every allocation goes through `Checked`, which lays it out as
+//!
+//! ```text
+//! [Header 64 | front canary 16 | data | back canary 16]
+//! ```
+//!
+//! The header links the block into the list of live blocks and keeps up to
+//! `SITES` return addresses of the code that allocated it, as offsets into
+//! the kernel image (`addr2line -e redux_kernel.efi 0x...`; the make target
+//! turns on the frame pointers this needs). Freeing checks both canaries,
+//! fills the data with `POISON` and keeps the block in a quarantine of
+//! `QUARANTINE_BYTES` before really freeing it; anything written to it
+//! meanwhile is a use after free. Every `SWEEP_MS` a timer checks all live
+//! and quarantined blocks, and `heapcheck` in the shell does it on demand.
+//!
+//! The allocator can't log (logging allocates), so faults go to a small
+//! ring and the next sweep logs them.
+
+use alloc::alloc::{GlobalAlloc, Layout};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::spinlock::SpinLock;
+
+const HEADER: usize = core::mem::size_of::<Header>();
+const CANARY: usize = 16;
+/// Return addresses kept per allocation.
+pub const SITES: usize = 3;
+const FRONT_BYTE: u8 = 0xFB;
+const BACK_BYTE: u8 = 0xBB;
+pub const POISON: u8 = 0xDD;
+const LIVE: u32 = u32::from_le_bytes(*b"LIVE");
+const FREED: u32 = u32::from_le_bytes(*b"FREE");
+/// Freed bytes held back before they can be reused.
+const QUARANTINE_BYTES: usize = 8 * 1024 * 1024;
+const SWEEP_MS: u64 = 5_000;
+const MAX_FAULTS: usize = 32;
+/// How far above the stack pointer a frame pointer may be.
+const STACK_SPAN: usize = 1024 * 1024;
+
+/// Kernel image range, for turning return addresses into offsets. Zero until
+/// `init`, and then call sites are not recorded.
+static IMAGE_BASE: AtomicUsize = AtomicUsize::new(0);
+static IMAGE_END: AtomicUsize = AtomicUsize::new(0);
+
+#[repr(C)]
+struct Header {
+    prev: usize,
+    next: usize,
+    size: usize,
+    magic: u32,
+    align: u32,
+    pre: u32,
+    /// Set once a fault in this block was reported, so sweeps don't repeat it.
+    reported: u32,
+    sites: [u64; SITES],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// Something wrote just before the block.
+    Front,
+    /// Something wrote past its end.
+    Back,
+    /// Written after it was freed.
+    UseAfterFree,
+    DoubleFree,
+    /// Freed with a pointer the allocator never returned.
+    BadFree,
+}
+
+impl Fault {
+    fn text(self) -> &'static str {
+        match self {
+            Fault::Front => "escritura antes del bloque",
+            Fault::Back => "escritura pasado el final",
+            Fault::UseAfterFree => "escritura tras liberar",
+            Fault::DoubleFree => "doble liberacion",
+            Fault::BadFree => "liberacion de un puntero desconocido",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Report {
+    pub fault: Fault,
+    pub addr: usize,
+    pub size: usize,
+    pub sites: [u64; SITES],
+}
+
+impl Report {
+    pub fn line(&self) -> String {
+        let mut line = alloc::format!(
+            "heapcheck: {} en {:#x} ({} bytes), asignado en",
+            self.fault.text(),
+            self.addr,
+            self.size
+        );
+        let mut any = false;
+        for site in self.sites.iter().filter(|site| **site != 0) {
+            line.push_str(alloc::format!(" +{:#x}", site).as_str());
+            any = true;
+        }
+        if !any {
+            line.push_str(" (desconocido)");
+        }
+        line
+    }
+}
+
+/// Outer layout for a block holding `layout`, and the offset of its data.
+pub fn outer(layout: Layout) -> Option<(Layout, usize)> {
+    let align = layout.align().max(CANARY);
+    let pre = (HEADER + CANARY).next_multiple_of(align);
+    let total = pre.checked_add(layout.size())?.checked_add(CANARY)?;
+    Some((Layout::from_size_align(total, align).ok()?, pre))
+}
+
+unsafe fn header(user: *mut u8) -> *mut Header {
+    unsafe { user.sub(CANARY + HEADER) as *mut Header }
+}
+
+/// Set up the header and canaries of a block at `base`; returns its data.
+///
+/// # Safety
+/// `base` must point to `outer(layout)` writable bytes.
+pub unsafe fn arm(base: *mut u8, layout: Layout, pre: usize, sites: [u64; SITES]) -> *mut u8 {
+    unsafe {
+        let user = base.add(pre);
+        header(user).write(Header {
+            prev: 0,
+            next: 0,
+            size: layout.size(),
+            magic: LIVE,
+            align: layout.align().max(CANARY) as u32,
+            pre: pre as u32,
+            reported: 0,
+            sites,
+        });
+        core::ptr::write_bytes(user.sub(CANARY), FRONT_BYTE, CANARY);
+        core::ptr::write_bytes(user.add(layout.size()), BACK_BYTE, CANARY);
+        user
+    }
+}
+
+fn all(ptr: *const u8, len: usize, byte: u8) -> bool {
+    unsafe { core::slice::from_raw_parts(ptr, len) }
+        .iter()
+        .all(|b| *b == byte)
+}
+
+/// What is wrong with the live block `user`, if anything.
+///
+/// # Safety
+/// `user` must come from `arm`, its memory still mapped.
+pub unsafe fn verify(user: *mut u8) -> Option<Fault> {
+    unsafe {
+        let head = &*header(user);
+        match head.magic {
+            LIVE => {}
+            FREED => return Some(Fault::DoubleFree),
+            _ => return Some(Fault::BadFree),
+        }
+        if !all(user.sub(CANARY), CANARY, FRONT_BYTE) {
+            return Some(Fault::Front);
+        }
+        if !all(user.add(head.size), CANARY, BACK_BYTE) {
+            return Some(Fault::Back);
+        }
+        None
+    }
+}
+
+/// Mark `user` freed and fill it with `POISON`.
+///
+/// # Safety
+/// As for `verify`, and `user` must be live.
+pub unsafe fn poison(user: *mut u8) {
+    unsafe {
+        let head = &mut *header(user);
+        head.magic = FREED;
+        core::ptr::write_bytes(user, POISON, head.size);
+    }
+}
+
+/// Whether a quarantined block is still as `poison` left it.
+///
+/// # Safety
+/// As for `verify`.
+pub unsafe fn poison_intact(user: *mut u8) -> bool {
+    unsafe {
+        let head = &*header(user);
+        head.magic == FREED && all(user, head.size, POISON) && all(user.add(head.size), CANARY, BACK_BYTE)
+    }
+}
+
+/// The live list and the quarantine, linked through the headers. Addresses
+/// are data pointers.
+struct Lists {
+    live: usize,
+    live_blocks: usize,
+    live_bytes: usize,
+    /// Oldest and newest quarantined block.
+    oldest: usize,
+    newest: usize,
+    quarantined: usize,
+}
+
+impl Lists {
+    const fn new() -> Self {
+        Self {
+            live: 0,
+            live_blocks: 0,
+            live_bytes: 0,
+            oldest: 0,
+            newest: 0,
+            quarantined: 0,
+        }
+    }
+
+    unsafe fn link(&mut self, user: *mut u8) {
+        unsafe {
+            let head = &mut *header(user);
+            head.next = self.live;
+            if self.live != 0 {
+                (*header(self.live as *mut u8)).prev = user as usize;
+            }
+            self.live = user as usize;
+            self.live_blocks += 1;
+            self.live_bytes += head.size;
+        }
+    }
+
+    unsafe fn unlink(&mut self, user: *mut u8) {
+        unsafe {
+            let head = &mut *header(user);
+            if head.prev != 0 {
+                (*header(head.prev as *mut u8)).next = head.next;
+            } else {
+                self.live = head.next;
+            }
+            if head.next != 0 {
+                (*header(head.next as *mut u8)).prev = head.prev;
+            }
+            head.prev = 0;
+            head.next = 0;
+            self.live_blocks -= 1;
+            self.live_bytes -= head.size;
+        }
+    }
+
+    unsafe fn quarantine(&mut self, user: *mut u8) {
+        unsafe {
+            if self.newest != 0 {
+                (*header(self.newest as *mut u8)).next = user as usize;
+            } else {
+                self.oldest = user as usize;
+            }
+            self.newest = user as usize;
+            self.quarantined += (*header(user)).size;
+        }
+    }
+
+    unsafe fn release_oldest(&mut self) -> Option<*mut u8> {
+        if self.oldest == 0 {
+            return None;
+        }
+        let user = self.oldest as *mut u8;
+        unsafe {
+            let head = &mut *header(user);
+            self.oldest = head.next;
+            if self.oldest == 0 {
+                self.newest = 0;
+            }
+            self.quarantined -= head.size;
+            head.next = 0;
+        }
+        Some(user)
+    }
+}
+
+struct Faults {
+    ring: [Option<Report>; MAX_FAULTS],
+    next: usize,
+    total: u64,
+    /// Not logged yet.
+    pending: usize,
+}
+
+static LISTS: SpinLock<Lists> = SpinLock::new(Lists::new());
+static FAULTS: SpinLock<Faults> = SpinLock::new(Faults {
+    ring: [None; MAX_FAULTS],
+    next: 0,
+    total: 0,
+    pending: 0,
+});
+
+/// Remember a fault in `user`, once per block.
+unsafe fn record(fault: Fault, user: *mut u8) {
+    let (size, sites) = unsafe {
+        let head = &mut *header(user);
+        if fault != Fault::BadFree {
+            if head.reported != 0 {
+                return;
+            }
+            head.reported = 1;
+        }
+        match fault {
+            Fault::BadFree => (0, [0; SITES]),
+            _ => (head.size, head.sites),
+        }
+    };
+    let mut faults = FAULTS.lock();
+    let slot = faults.next;
+    faults.ring[slot] = Some(Report {
+        fault,
+        addr: user as usize,
+        size,
+        sites,
+    });
+    faults.next = (slot + 1) % MAX_FAULTS;
+    faults.total += 1;
+    faults.pending = (faults.pending + 1).min(MAX_FAULTS);
+}
+
+/// Return addresses above the allocator, as image offsets, by walking the
+/// frame pointer chain. Stops at anything that doesn't look like a frame.
+#[inline(never)]
+fn call_sites() -> [u64; SITES] {
+    let mut out = [0u64; SITES];
+    let base = IMAGE_BASE.load(Ordering::Relaxed);
+    let end = IMAGE_END.load(Ordering::Relaxed);
+    if base == 0 {
+        return out;
+    }
+    let (mut fp, sp): (usize, usize);
+    unsafe {
+        core::arch::asm!("mov {}, rbp", out(reg) fp, options(nomem, nostack));
+        core::arch::asm!("mov {}, rsp", out(reg) sp, options(nomem, nostack));
+    }
+    // The first frame returns into `Checked::alloc` itself.
+    let mut skip = 1;
+    let mut found = 0;
+    while found < SITES {
+        if fp < sp || fp >= sp + STACK_SPAN || fp % 8 != 0 {
+            break;
+        }
+        let (next, ret) = unsafe { (*(fp as *const usize), *((fp + 8) as *const usize)) };
+        if ret < base || ret >= end {
+            break;
+        }
+        if skip > 0 {
+            skip -= 1;
+        } else {
+            out[found] = (ret - base) as u64;
+            found += 1;
+        }
+        if next <= fp {
+            break;
+        }
+        fp = next;
+    }
+    out
+}
+
+/// The global allocator of `heapcheck` builds, over `crate::allocator`.
+pub struct Checked;
+
+unsafe impl GlobalAlloc for Checked {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some((outer, pre)) = outer(layout) else {
+            return core::ptr::null_mut();
+        };
+        let sites = call_sites();
+        unsafe {
+            let base = crate::allocator::raw_alloc(outer);
+            if base.is_null() {
+                return base;
+            }
+            let user = arm(base, layout, pre, sites);
+            LISTS.lock().link(user);
+            user
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let mut lists = LISTS.lock();
+        unsafe {
+            match verify(ptr) {
+                // Freeing these would corrupt the heap; leak them instead.
+                Some(fault @ (Fault::DoubleFree | Fault::BadFree)) => {
+                    record(fault, ptr);
+                    return;
+                }
+                Some(fault) => record(fault, ptr),
+                None => {}
+            }
+            lists.unlink(ptr);
+            if layout.size() > QUARANTINE_BYTES / 4 {
+                let head = &*header(ptr);
+                let outer =
+                    Layout::from_size_align_unchecked(head.pre as usize + head.size + CANARY, head.align as usize);
+                crate::allocator::raw_dealloc(ptr.sub(head.pre as usize), outer);
+                return;
+            }
+            poison(ptr);
+            lists.quarantine(ptr);
+            while lists.quarantined > QUARANTINE_BYTES {
+                let Some(old) = lists.release_oldest() else {
+                    break;
+                };
+                if !poison_intact(old) {
+                    record(Fault::UseAfterFree, old);
+                }
+                let head = &*header(old);
+                let outer =
+                    Layout::from_size_align_unchecked(head.pre as usize + head.size + CANARY, head.align as usize);
+                crate::allocator::raw_dealloc(old.sub(head.pre as usize), outer);
+            }
+        }
+    }
+}
+
+pub fn enabled() -> bool {
+    cfg!(feature = "heapcheck")
+}
+
+/// Check every live and quarantined block now. Returns how many new faults
+/// it found.
+pub fn sweep() -> u64 {
+    if !enabled() {
+        return 0;
+    }
+    let before = FAULTS.lock().total;
+    {
+        let lists = LISTS.lock();
+        let mut user = lists.live;
+        while user != 0 {
+            unsafe {
+                if let Some(fault) = verify(user as *mut u8) {
+                    record(fault, user as *mut u8);
+                }
+                user = (*header(user as *mut u8)).next;
+            }
+        }
+        let mut user = lists.oldest;
+        while user != 0 {
+            unsafe {
+                if !poison_intact(user as *mut u8) {
+                    record(Fault::UseAfterFree, user as *mut u8);
+                }
+                user = (*header(user as *mut u8)).next;
+            }
+        }
+    }
+    FAULTS.lock().total - before
+}
+
+/// Log the faults found since the last call.
+pub fn flush() {
+    let pending: Vec<Report> = {
+        let mut faults = FAULTS.lock();
+        let count = core::mem::take(&mut faults.pending);
+        (0..count)
+            .filter_map(|back| faults.ring[(faults.next + MAX_FAULTS - 1 - back) % MAX_FAULTS])
+            .rev()
+            .collect()
+    };
+    for report in pending.iter() {
+        crate::klog::log(crate::klog::Level::Error, report.line().as_str());
+    }
+}
+
+fn on_sweep(_arg: usize) {
+    sweep();
+    flush();
+}
+
+/// Note where the kernel image is and start the periodic sweep. Needs the
+/// timer, so it runs after the interrupt setup.
+pub fn init() {
+    if !enabled() {
+        return;
+    }
+    if let Some((base, size)) = crate::wx::kernel_image() {
+        IMAGE_END.store((base + size) as usize, Ordering::Relaxed);
+        IMAGE_BASE.store(base as usize, Ordering::Relaxed);
+    }
+    crate::timer::every_ms(SWEEP_MS, on_sweep, 0);
+    crate::klog::log(
+        crate::klog::Level::Info,
+        alloc::format!(
+            "heapcheck: canarios de {} bytes, cuarentena de {} MiB, revision cada {} s",
+            CANARY,
+            QUARANTINE_BYTES >> 20,
+            SWEEP_MS / 1000
+        )
+        .as_str(),
+    );
+}
+
+/// `heapcheck`: sweep now and show the counters and the last faults.
+pub fn command_lines(_args: &str) -> Vec<String> {
+    if !enabled() {
+        return alloc::vec![String::from(
+            "heapcheck: no compilado en este kernel (make KERNEL_FEATURES=heapcheck)"
+        )];
+    }
+    let found = sweep();
+    flush();
+    let (blocks, bytes, quarantined) = {
+        let lists = LISTS.lock();
+        (lists.live_blocks, lists.live_bytes, lists.quarantined)
+    };
+    let (total, recent): (u64, Vec<Report>) = {
+        let faults = FAULTS.lock();
+        (faults.total, faults.ring.iter().flatten().copied().collect())
+    };
+    let mut out = alloc::vec![
+        alloc::format!("Bloques vivos: {} ({} KiB)", blocks, bytes / 1024),
+        alloc::format!("En cuarentena: {} KiB", quarantined / 1024),
+        alloc::format!("Fallos: {} ({} en esta revision)", total, found),
+    ];
+    out.extend(recent.iter().map(|report| report.line()));
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "heapcheck";
+
+    fn canaries_catch_overruns() {
+        let layout = Layout::from_size_align(24, 8).map_err(|_| "layout")?;
+        let (outer, pre) = outer(layout).ok_or("outer")?;
+        let mut block = alloc::vec![0u8; outer.size() + outer.align()];
+        let base = block.as_mut_ptr();
+        let base = unsafe { base.add(base.align_offset(outer.align())) };
+        unsafe {
+            let user = arm(base, layout, pre, [0x1234, 0, 0]);
+            crate::selftest::ensure_eq(user as usize % 16, 0, "alineado")?;
+            crate::selftest::ensure_eq(verify(user), None, "intacto")?;
+            *user.add(24) = 0;
+            crate::selftest::ensure_eq(verify(user), Some(Fault::Back), "desborde")?;
+            *user.add(24) = BACK_BYTE;
+            *user.sub(1) = 0;
+            crate::selftest::ensure_eq(verify(user), Some(Fault::Front), "antes del bloque")?;
+            *user.sub(1) = FRONT_BYTE;
+            poison(user);
+            crate::selftest::ensure(poison_intact(user), "veneno")?;
+            crate::selftest::ensure_eq(verify(user), Some(Fault::DoubleFree), "doble liberacion")?;
+            *user.add(3) = 7;
+            crate::selftest::ensure(!poison_intact(user), "uso tras liberar")
+        }
+    }
+
+    fn big_alignments_fit() {
+        for align in [1usize, 16, 64, 4096] {
+            let layout = Layout::from_size_align(100, align).map_err(|_| "layout")?;
+            let (outer, pre) = outer(layout).ok_or("outer")?;
+            crate::selftest::ensure_eq(pre % align.max(CANARY), 0, "datos alineados")?;
+            crate::selftest::ensure(pre >= HEADER + CANARY, "sitio para cabecera")?;
+            crate::selftest::ensure_eq(outer.size(), pre + 100 + CANARY, "tamano")?;
+        }
+        let report = Report {
+            fault: Fault::Back,
+            addr: 0x1000,
+            size: 8,
+            sites: [0x2a, 0, 0],
+        };
+        crate::selftest::ensure(report.line().contains("+0x2a"), "sitio")
+    }
+}
@@ -0,0 +1,295 @@
+//! `/host` shared folder backed by the virtio-9p export.
+//!
+//! Paths are accepted with or without the `/host` prefix. The active FAT
+//! volume is still the shell's working filesystem; `host get`/`host put` copy
+//! files between it and the host.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::virtio::p9::{self, P9Client, O_CREAT, O_RDONLY, O_TRUNC, O_WRONLY};
+
+pub const HOST_MOUNT: &str = "/host";
+
+const HOST_FILE_MODE: u32 = 0o644;
+const HOST_DIR_MODE: u32 = 0o755;
+const HOST_CAT_LIMIT: usize = 16 * 1024;
+
+pub struct HostEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+/// Split a path into components below the export root, resolving `.` and `..`.
+fn components(path: &str) -> Vec<&str> {
+    let path = path.trim();
+    let rel = path.strip_prefix(HOST_MOUNT).unwrap_or(path);
+    let mut out: Vec<&str> = Vec::new();
+    for part in rel.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                out.pop();
+            }
+            name => out.push(name),
+        }
+    }
+    out
+}
+
+/// # Safety
+///
+/// As for `p9::client`: kernel thread, one borrow in use at a time.
+unsafe fn client() -> Result<&'static mut P9Client, &'static str> {
+    unsafe { p9::client() }.ok_or("/host no montado (arranca QEMU con virtio-9p-pci, mount_tag=host)")
+}
+
+fn with_fid<T>(
+    c: &mut P9Client,
+    path: &[&str],
+    f: impl FnOnce(&mut P9Client, u32) -> Result<T, &'static str>,
+) -> Result<T, &'static str> {
+    let fid = c.walk(path)?;
+    let result = f(c, fid);
+    c.clunk(fid);
+    result
+}
+
+pub fn stat(path: &str) -> Result<HostEntry, &'static str> {
+    // SAFETY: kernel thread; the borrow ends with this function.
+    let c = unsafe { client() }?;
+    let parts = components(path);
+    let attr = with_fid(c, &parts, |c, fid| c.getattr(fid))?;
+    Ok(HostEntry {
+        name: String::from(parts.last().copied().unwrap_or(HOST_MOUNT)),
+        is_dir: attr.qid.is_dir(),
+        size: attr.size,
+    })
+}
+
+pub fn list_dir(path: &str) -> Result<Vec<HostEntry>, &'static str> {
+    // SAFETY: kernel thread; the borrow ends with this function.
+    let c = unsafe { client() }?;
+    let parts = components(path);
+    let names = with_fid(c, &parts, |c, fid| {
+        c.lopen(fid, O_RDONLY)?;
+        c.readdir(fid)
+    })?;
+
+    let mut out = Vec::with_capacity(names.len());
+    for entry in names.into_iter() {
+        let mut child: Vec<&str> = parts.clone();
+        child.push(entry.name.as_str());
+        let size = if entry.qid.is_dir() {
+            0
+        } else {
+            with_fid(c, &child, |c, fid| c.getattr(fid)).map(|a| a.size).unwrap_or(0)
+        };
+        out.push(HostEntry { is_dir: entry.qid.is_dir(), size, name: entry.name });
+    }
+    out.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    Ok(out)
+}
+
+pub fn read_file(path: &str) -> Result<Vec<u8>, &'static str> {
+    // SAFETY: kernel thread; the borrow ends with this function.
+    let c = unsafe { client() }?;
+    let parts = components(path);
+    with_fid(c, &parts, |c, fid| {
+        let attr = c.getattr(fid)?;
+        if attr.qid.is_dir() {
+            return Err("9P: is a directory");
+        }
+        c.lopen(fid, O_RDONLY)?;
+        let mut data = Vec::with_capacity(attr.size as usize);
+        loop {
+            let chunk = c.read(fid, data.len() as u64, u32::MAX)?;
+            if chunk.is_empty() {
+                break;
+            }
+            data.extend_from_slice(&chunk);
+        }
+        Ok(data)
+    })
+}
+
+/// Create or truncate `path` and write `content` to it.
+pub fn write_file(path: &str, content: &[u8]) -> Result<(), &'static str> {
+    // SAFETY: kernel thread; the borrow ends with this function.
+    let c = unsafe { client() }?;
+    let parts = components(path);
+    let Some((name, parent)) = parts.split_last() else {
+        return Err("Ruta invalida.");
+    };
+
+    let fid = match c.walk(&parts) {
+        Ok(fid) => {
+            if let Err(e) = c.lopen(fid, O_WRONLY | O_TRUNC) {
+                c.clunk(fid);
+                return Err(e);
+            }
+            fid
+        }
+        Err(_) => {
+            let fid = c.walk(parent)?;
+            if let Err(e) = c.lcreate(fid, name, O_WRONLY | O_CREAT | O_TRUNC, HOST_FILE_MODE) {
+                c.clunk(fid);
+                return Err(e);
+            }
+            fid
+        }
+    };
+
+    let mut written = 0usize;
+    let mut result = Ok(());
+    while written < content.len() {
+        match c.write(fid, written as u64, &content[written..]) {
+            Ok(0) => {
+                result = Err("9P: short write");
+                break;
+            }
+            Ok(n) => written += n,
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        }
+    }
+    c.clunk(fid);
+    result
+}
+
+pub fn mkdir(path: &str) -> Result<(), &'static str> {
+    // SAFETY: kernel thread; the borrow ends with this function.
+    let c = unsafe { client() }?;
+    let parts = components(path);
+    let Some((name, parent)) = parts.split_last() else {
+        return Err("Ruta invalida.");
+    };
+    with_fid(c, parent, |c, fid| c.mkdir(fid, name, HOST_DIR_MODE).map(|_| ()))
+}
+
+pub fn remove(path: &str) -> Result<(), &'static str> {
+    let is_dir = stat(path)?.is_dir;
+    // SAFETY: kernel thread; the borrow ends with this function.
+    let c = unsafe { client() }?;
+    let parts = components(path);
+    let Some((name, parent)) = parts.split_last() else {
+        return Err("No se puede borrar la raiz de /host.");
+    };
+    with_fid(c, parent, |c, fid| c.unlinkat(fid, name, is_dir))
+}
+
+fn display_path(path: &str) -> String {
+    let parts = components(path);
+    if parts.is_empty() {
+        return String::from(HOST_MOUNT);
+    }
+    let mut out = String::from(HOST_MOUNT);
+    for part in parts.iter() {
+        out.push('/');
+        out.push_str(part);
+    }
+    out
+}
+
+pub fn ls_lines(path: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    match list_dir(path) {
+        Ok(entries) => {
+            out.push(alloc::format!("{}:", display_path(path)));
+            for entry in entries.iter() {
+                out.push(alloc::format!(
+                    "  [{}] {} ({} bytes)",
+                    if entry.is_dir { "DIR " } else { "FILE" },
+                    entry.name,
+                    entry.size
+                ));
+            }
+            if entries.is_empty() {
+                out.push(String::from("  (vacio)"));
+            }
+        }
+        Err(e) => out.push(String::from(e)),
+    }
+    out
+}
+
+pub fn cat_lines(path: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    match read_file(path) {
+        Ok(data) => {
+            let shown = &data[..data.len().min(HOST_CAT_LIMIT)];
+            match core::str::from_utf8(shown) {
+                Ok(text) => out.extend(text.lines().map(String::from)),
+                Err(_) => out.push(String::from("<binary>")),
+            }
+            if data.len() > HOST_CAT_LIMIT {
+                out.push(String::from("[output truncated]"));
+            }
+        }
+        Err(e) => out.push(String::from(e)),
+    }
+    out
+}
+
+pub fn status_line() -> String {
+    // SAFETY: kernel thread; the borrow ends with the match.
+    match unsafe { p9::client() } {
+        Some(c) => alloc::format!("{} montado -> {}", HOST_MOUNT, c.status_line()),
+        None => alloc::format!("{}: sin export virtio-9p", HOST_MOUNT),
+    }
+}
+
+/// Shared implementation of the `host` command. FAT-side paths are relative to `dir_cluster`.
+pub fn command_lines(args: &str, dir_cluster: u32) -> Vec<String> {
+    let mut parts = args.split_whitespace();
+    let sub = parts.next().unwrap_or("status");
+    let a = parts.next();
+    let b = parts.next();
+    let mut out = Vec::new();
+
+    match (sub, a, b) {
+        ("status", _, _) => out.push(status_line()),
+        ("ls", path, _) => out.extend(ls_lines(path.unwrap_or(HOST_MOUNT))),
+        ("cat", Some(path), _) => out.extend(cat_lines(path)),
+        ("mkdir", Some(path), _) => match mkdir(path) {
+            Ok(()) => out.push(alloc::format!("Creado {}", display_path(path))),
+            Err(e) => out.push(String::from(e)),
+        },
+        ("rm", Some(path), _) => match remove(path) {
+            Ok(()) => out.push(alloc::format!("Borrado {}", display_path(path))),
+            Err(e) => out.push(String::from(e)),
+        },
+        ("get", Some(src), dst) => {
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+            let name = dst.unwrap_or_else(|| components(src).last().copied().unwrap_or(""));
+            let result = read_file(src).and_then(|data| {
+                fat.write_text_file_in_dir(dir_cluster, name, &data).map(|_| data.len())
+            });
+            match result {
+                Ok(len) => out.push(alloc::format!("{} -> {} ({} bytes)", display_path(src), name, len)),
+                Err(e) => out.push(String::from(e)),
+            }
+        }
+        ("put", Some(src), dst) => {
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+            let target = match dst {
+                Some(dst) => String::from(dst),
+                None => alloc::format!("{}/{}", HOST_MOUNT, src),
+            };
+            let result = fat
+                .read_file_in_dir(dir_cluster, src)
+                .and_then(|data| write_file(target.as_str(), &data).map(|_| data.len()));
+            match result {
+                Ok(len) => out.push(alloc::format!("{} -> {} ({} bytes)", src, display_path(target.as_str()), len)),
+                Err(e) => out.push(String::from(e)),
+            }
+        }
+        _ => out.push(String::from(
+            "Uso: host [status|ls [ruta]|cat <ruta>|get <ruta_host> [archivo]|put <archivo> [ruta_host]|mkdir <ruta>|rm <ruta>]",
+        )),
+    }
+    out
+}
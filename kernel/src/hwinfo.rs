@@ -0,0 +1,307 @@
+//! Hardware inventory: CPU, memory, PCI, storage, display and network.
+//!
+//! Backs the `hwinfo` shell command and the GUI "About this PC" panel.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::arch::x86_64::__cpuid;
+
+pub struct CpuSummary {
+    pub vendor: String,
+    pub brand: String,
+    pub family: u32,
+    pub model: u32,
+    pub stepping: u32,
+    pub features: Vec<&'static str>,
+}
+
+fn push_reg_bytes(out: &mut Vec<u8>, reg: u32) {
+    out.extend_from_slice(&reg.to_le_bytes());
+}
+
+fn bytes_to_trimmed(bytes: &[u8]) -> String {
+    let text: String = bytes
+        .iter()
+        .take_while(|b| **b != 0)
+        .map(|b| if b.is_ascii_graphic() || *b == b' ' { *b as char } else { ' ' })
+        .collect();
+    String::from(text.trim())
+}
+
+pub fn cpu_summary() -> CpuSummary {
+    let leaf0 = unsafe { __cpuid(0) };
+    let mut vendor = Vec::with_capacity(12);
+    push_reg_bytes(&mut vendor, leaf0.ebx);
+    push_reg_bytes(&mut vendor, leaf0.edx);
+    push_reg_bytes(&mut vendor, leaf0.ecx);
+
+    let max_ext = unsafe { __cpuid(0x8000_0000) }.eax;
+    let mut brand = Vec::with_capacity(48);
+    if max_ext >= 0x8000_0004 {
+        for leaf in 0x8000_0002u32..=0x8000_0004 {
+            let r = unsafe { __cpuid(leaf) };
+            push_reg_bytes(&mut brand, r.eax);
+            push_reg_bytes(&mut brand, r.ebx);
+            push_reg_bytes(&mut brand, r.ecx);
+            push_reg_bytes(&mut brand, r.edx);
+        }
+    }
+
+    let leaf1 = unsafe { __cpuid(1) };
+    let base_family = (leaf1.eax >> 8) & 0xF;
+    let base_model = (leaf1.eax >> 4) & 0xF;
+    let family = if base_family == 0xF {
+        base_family + ((leaf1.eax >> 20) & 0xFF)
+    } else {
+        base_family
+    };
+    let model = if base_family == 0x6 || base_family == 0xF {
+        base_model | (((leaf1.eax >> 16) & 0xF) << 4)
+    } else {
+        base_model
+    };
+
+    let mut features = Vec::new();
+    let edx_flags: [(u32, &str); 3] = [(25, "sse"), (26, "sse2"), (28, "htt")];
+    let ecx_flags: [(u32, &str); 9] = [
+        (0, "sse3"),
+        (9, "ssse3"),
+        (19, "sse4.1"),
+        (20, "sse4.2"),
+        (23, "popcnt"),
+        (25, "aes-ni"),
+        (28, "avx"),
+        (30, "rdrand"),
+        (31, "hypervisor"),
+    ];
+    for (bit, name) in edx_flags.iter() {
+        if leaf1.edx & (1 << bit) != 0 {
+            features.push(*name);
+        }
+    }
+    for (bit, name) in ecx_flags.iter() {
+        if leaf1.ecx & (1 << bit) != 0 {
+            features.push(*name);
+        }
+    }
+    if leaf0.eax >= 7 {
+        let leaf7 = unsafe { core::arch::x86_64::__cpuid_count(7, 0) };
+        let ebx_flags: [(u32, &str); 6] = [
+            (5, "avx2"),
+            (7, "smep"),
+            (16, "avx512f"),
+            (18, "rdseed"),
+            (20, "smap"),
+            (29, "sha-ni"),
+        ];
+        for (bit, name) in ebx_flags.iter() {
+            if leaf7.ebx & (1 << bit) != 0 {
+                features.push(*name);
+            }
+        }
+    }
+
+    CpuSummary {
+        vendor: bytes_to_trimmed(&vendor),
+        brand: bytes_to_trimmed(&brand),
+        family,
+        model,
+        stepping: leaf1.eax & 0xF,
+        features,
+    }
+}
+
+fn format_mac(mac: [u8; 6]) -> String {
+    alloc::format!(
+        "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
+        mac[0],
+        mac[1],
+        mac[2],
+        mac[3],
+        mac[4],
+        mac[5]
+    )
+}
+
+pub fn cpu_lines() -> Vec<String> {
+    let cpu = cpu_summary();
+    let mut out = Vec::new();
+    out.push(String::from("CPU:"));
+    out.push(alloc::format!(
+        "  {}",
+        if cpu.brand.is_empty() { cpu.vendor.as_str() } else { cpu.brand.as_str() }
+    ));
+    out.push(alloc::format!(
+        "  vendor={} family={:#x} model={:#x} stepping={} nucleos={}",
+        cpu.vendor,
+        cpu.family,
+        cpu.model,
+        cpu.stepping,
+        crate::smp::cpu_count().max(1)
+    ));
+    let mut flags = String::from("  flags:");
+    for name in cpu.features.iter() {
+        flags.push(' ');
+        flags.push_str(name);
+    }
+    out.push(flags);
+    out
+}
+
+pub fn memory_lines() -> Vec<String> {
+    let stats = crate::memory::stats();
+    let heap = crate::allocator::heap_size_bytes() as u64;
+    let mut out = Vec::new();
+    out.push(String::from("Memoria:"));
+    out.push(alloc::format!(
+        "  total mapa UEFI: {}, convencional: {}",
+        crate::i18n::format::size(stats.total_bytes()),
+        crate::i18n::format::size(stats.conventional_bytes())
+    ));
+    out.push(alloc::format!("  heap del kernel: {}", crate::i18n::format::size(heap)));
+    out
+}
+
+pub fn pci_lines() -> Vec<String> {
+    let devices = crate::pci::devices();
+    let mut out = Vec::new();
+    out.push(alloc::format!("PCI ({} funciones):", devices.len()));
+    for info in devices.iter() {
+        let dev = info.device;
+        let vendor = crate::pci_ids::vendor_name(dev.vendor_id).unwrap_or("?");
+        let name = crate::pci_ids::device_name(dev.vendor_id, dev.device_id)
+            .unwrap_or(crate::pci_ids::class_name(info.class_code, info.sub_class));
+        out.push(alloc::format!(
+            "  {:02x}:{:02x}.{} {:04x}:{:04x} {} {}{}",
+            dev.bus,
+            dev.slot,
+            dev.func,
+            dev.vendor_id,
+            dev.device_id,
+            vendor,
+            name,
+            info.driver.map(|d| alloc::format!(" [{}]", d)).unwrap_or_default()
+        ));
+    }
+    out
+}
+
+pub fn storage_lines() -> Vec<String> {
+    let mut out = Vec::new();
+    out.push(String::from("Almacenamiento:"));
+    if crate::nvme::is_ready() {
+        out.push(String::from("  NVMe: controlador activo"));
+    }
+    if crate::virtio::block::is_ready() {
+        out.push(alloc::format!("  {}", crate::virtio::block::status_line()));
+    }
+    if crate::virtio::p9::is_mounted() {
+        out.push(alloc::format!("  {}", crate::hostfs::status_line()));
+    }
+    if crate::runtime::runtime_uefi_active() {
+        for dev in crate::fat32::Fat32::detect_uefi_block_devices().iter() {
+            out.push(alloc::format!(
+                "  blk{}: {} MiB {}{}{}",
+                dev.index,
+                dev.total_mib,
+                dev.fs_kind.as_str(),
+                if dev.removable { " extraible" } else { "" },
+                if dev.logical_partition { " particion" } else { "" }
+            ));
+        }
+    }
+    if out.len() == 1 {
+        out.push(String::from("  (sin dispositivos detectados)"));
+    }
+    out
+}
+
+pub fn display_lines() -> Vec<String> {
+    let (width, height) = crate::framebuffer::dimensions();
+    let mut out = Vec::new();
+    out.push(String::from("Pantalla:"));
+    let (phys_w, phys_h) = crate::framebuffer::physical_dimensions();
+    out.push(alloc::format!("  framebuffer {}x{}", phys_w, phys_h));
+    let scale = crate::framebuffer::scale_percent();
+    if scale != 100 {
+        out.push(alloc::format!(
+            "  escala UI {} -> escritorio {}x{}",
+            crate::gui::hidpi::format_scale(scale),
+            width,
+            height
+        ));
+    }
+    out
+}
+
+pub fn network_lines() -> Vec<String> {
+    let mut out = Vec::new();
+    out.push(String::from("Red:"));
+    if let Some(mac) = crate::intel_net::get_mac_address() {
+        out.push(alloc::format!(
+            "  {} MAC {}",
+            crate::intel_net::get_model_name().unwrap_or("Intel Ethernet"),
+            format_mac(mac)
+        ));
+    }
+    if let Some(mac) = crate::virtio::net::mac_address() {
+        out.push(alloc::format!("  VirtIO net MAC {}", format_mac(mac)));
+    }
+    if crate::intel_wifi::is_present() {
+        out.push(alloc::format!(
+            "  Wi-Fi {}",
+            crate::intel_wifi::get_model_name().unwrap_or("Intel Wireless")
+        ));
+    }
+    if out.len() == 1 {
+        out.push(String::from("  (sin adaptadores)"));
+    }
+    out
+}
+
+pub fn input_lines() -> Vec<String> {
+    let mut out = Vec::new();
+    out.push(String::from("Entrada:"));
+    for line in crate::virtio::input::status_lines() {
+        out.push(alloc::format!("  {}", line));
+    }
+    for line in crate::gamepad::status_lines() {
+        out.push(alloc::format!("  {}", line));
+    }
+    for line in crate::touch::status_lines() {
+        out.push(alloc::format!("  {}", line));
+    }
+    if out.len() == 1 {
+        out.push(String::from("  teclado/raton via UEFI o PS/2"));
+    }
+    out
+}
+
+/// Full inventory, one section after another.
+pub fn report_lines() -> Vec<String> {
+    let mut out = Vec::new();
+    out.extend(cpu_lines());
+    out.extend(memory_lines());
+    out.extend(display_lines());
+    out.extend(storage_lines());
+    out.extend(network_lines());
+    out.extend(input_lines());
+    out.extend(pci_lines());
+    out
+}
+
+/// Shared implementation of the `hwinfo [cpu|mem|pci|disk|display|net|input]` command.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let section = args.trim().to_ascii_lowercase();
+    match section.as_str() {
+        "" | "all" => report_lines(),
+        "cpu" => cpu_lines(),
+        "mem" | "memory" => memory_lines(),
+        "pci" => pci_lines(),
+        "disk" | "storage" => storage_lines(),
+        "display" | "gpu" => display_lines(),
+        "net" | "network" => network_lines(),
+        "input" => input_lines(),
+        _ => alloc::vec![String::from("Uso: hwinfo [cpu|mem|pci|disk|display|net|input|gui]")],
+    }
+}
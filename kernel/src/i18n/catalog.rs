@@ -0,0 +1,933 @@
+//! Built-in message catalog: `(key, Spanish, English)`.
+//!
+//! The 5x7 bitmap font only covers ASCII, so texts drawn on the framebuffer
+//! (installer, desktop) avoid accents. `{}` marks a `trf` argument.
+
+pub(super) const MESSAGES: &[(&str, &str, &str)] = &[
+    // Boot selector.
+    ("boot.title", "Zenox OS Boot Manager", "Zenox OS Boot Manager"),
+    ("boot.current", "Iniciar Zenox OS actual", "Start current Zenox OS"),
+    (
+        "boot.linux_guest",
+        "Iniciar Linux guest (apps Linux reales)",
+        "Start Linux guest (real Linux apps)",
+    ),
+    (
+        "boot.booting_current",
+        "Arranque: Zenox OS actual (Volumen {})...",
+        "Booting current Zenox OS (volume {})...",
+    ),
+    (
+        "boot.booting_installed",
+        "Arranque: Zenox OS instalado (Volumen {})...",
+        "Booting installed Zenox OS (volume {})...",
+    ),
+    (
+        "boot.booting_linux",
+        "Arranque: Linux guest...",
+        "Booting Linux guest...",
+    ),
+    ("boot.returned", "Arranque regresó desde {}.", "Boot returned from {}."),
+    (
+        "boot.failed_installed",
+        "No se pudo arrancar instalado: {}",
+        "Could not boot the installed system: {}",
+    ),
+    (
+        "boot.failed_linux",
+        "No se pudo arrancar Linux guest: {}",
+        "Could not boot the Linux guest: {}",
+    ),
+    (
+        "boot.firmware",
+        "Configuracion del firmware UEFI",
+        "UEFI firmware settings",
+    ),
+    (
+        "boot.recovery",
+        "Modo de recuperacion (consola, sin escritorio)",
+        "Recovery mode (console, no desktop)",
+    ),
+    (
+        "boot.booting_recovery",
+        "Arranque: modo de recuperacion...",
+        "Booting recovery mode...",
+    ),
+    (
+        "boot.safe_mode",
+        "Modo seguro (drivers minimos, consola)",
+        "Safe mode (minimal drivers, console)",
+    ),
+    (
+        "boot.booting_safe_mode",
+        "Arranque: modo seguro...",
+        "Booting safe mode...",
+    ),
+    (
+        "boot.hint",
+        "Flechas para elegir, Enter arranca, 1-9 directo, Esc la predeterminada.",
+        "Arrows to choose, Enter boots, 1-9 jumps, Esc takes the default.",
+    ),
+    ("boot.countdown", "Arranque automatico en {} s.", "Booting automatically in {} s."),
+    ("boot.booting_entry", "Arranque: {}...", "Booting {}..."),
+    (
+        "boot.failed_entry",
+        "No se pudo arrancar {}: {}",
+        "Could not boot {}: {}",
+    ),
+    (
+        "boot.continuing",
+        "Continuando con medio actual...",
+        "Continuing with the current media...",
+    ),
+    // Preboot installer.
+    (
+        "installer.error_title",
+        "ERROR DEL INSTALADOR PREBOOT",
+        "PREBOOT INSTALLER ERROR",
+    ),
+    (
+        "installer.error_esc",
+        "PULSA ESC PARA CONTINUAR A LA SHELL.",
+        "PRESS ESC TO CONTINUE TO SHELL.",
+    ),
+    (
+        "installer.bootstrap_title",
+        "INSTALADOR PREBOOT DE ZENOX OS",
+        "ZENOX OS PREBOOT INSTALLER",
+    ),
+    ("installer.wait", "ESPERA POR FAVOR...", "PLEASE WAIT..."),
+    (
+        "installer.title",
+        "INSTALADOR GRAFICO DE ZENOX OS (PRE-BOOT)",
+        "ZENOX OS GRAPHICAL INSTALLER (PRE-BOOT)",
+    ),
+    (
+        "installer.subtitle",
+        "PUEDE REDIMENSIONAR + CREAR PARTICIONES Y DESPUES INSTALAR EL PAQUETE DE ARRANQUE",
+        "CAN RESIZE + CREATE PARTITIONS, THEN INSTALL SYSTEM BOOT PACKAGE",
+    ),
+    ("installer.targets", "DESTINOS DE INSTALACION", "INSTALL TARGETS"),
+    (
+        "installer.summary",
+        "PAYLOAD {} KB   DISCOS {}   DESTINOS {}",
+        "PAYLOAD {} KB   DISKS {}   TARGETS {}",
+    ),
+    (
+        "installer.no_disks",
+        "NO SE DETECTARON DISCOS INTERNOS.",
+        "NO INTERNAL DISKS DETECTED.",
+    ),
+    (
+        "installer.no_partitions",
+        "NO HAY PARTICIONES. PULSA C PARA CREAR ARRANQUE FAT32 DE 16 GIB + RESTO DATOS EXFAT.",
+        "NO PARTITIONS FOUND. PRESS C TO CREATE 16 GIB FAT32 BOOT + REST EXFAT DATA.",
+    ),
+    (
+        "installer.more_targets",
+        "{} DESTINOS MAS SIN MOSTRAR",
+        "{} MORE TARGETS NOT SHOWN",
+    ),
+    (
+        "installer.keys",
+        "N/P MOVER  +/- TAMANO  C CREAR/DIVIDIR  R RECARGAR  1-9 ELEGIR  ENTER INSTALAR  ESC OMITIR",
+        "N/P MOVE  +/- RESIZE  C CREATE/SPLIT  R RELOAD  1-9 SELECT  ENTER INSTALL  ESC SKIP",
+    ),
+    (
+        "installer.keys_note",
+        "C USA ESPACIO LIBRE; EN DATOS/OTRA, C OTRA VEZ BORRA SOLO LA PARTICION ELEGIDA. PASO = 128 MIB.",
+        "C USES FREE SPACE; ON DATA/OTHER, C AGAIN ERASES ONLY SELECTED PARTITION. STEP = 128 MIB.",
+    ),
+    (
+        "installer.armed",
+        "CONFIRMACION ACTIVA: ENTER OTRA VEZ RESTABLECE DE FABRICA LA PARTICION ELEGIDA.",
+        "CONFIRMATION ARMED: ENTER AGAIN WILL FACTORY-RESET THE SELECTED PARTITION.",
+    ),
+    (
+        "installer.no_target",
+        "SIN DESTINO DE INSTALACION. CREA PRIMERO UNA PARTICION CON C.",
+        "NO INSTALL TARGET. CREATE A PARTITION FIRST WITH C.",
+    ),
+    (
+        "installer.danger",
+        "PELIGRO: ENTER OTRA VEZ PARA RESTABLECER DE FABRICA EL DESTINO {} E INSTALAR.",
+        "DANGER: ENTER AGAIN TO FACTORY-RESET TARGET {} AND INSTALL.",
+    ),
+    ("installer.preparing", "PREPARANDO DESTINO", "PREPARING TARGET"),
+    (
+        "installer.installing",
+        "INSTALANDO [{}%] {}. NO APAGUES EL EQUIPO.",
+        "INSTALLING [{}%] {}. DO NOT POWER OFF.",
+    ),
+    (
+        "installer.complete",
+        "INSTALACION COMPLETA. COPIADOS ARRANQUE + SISTEMA + LINUXRT({}) + SERVORT({}).",
+        "INSTALL COMPLETE. BOOT + SYSTEM + LINUXRT({}) + SERVORT({}) COPIED.",
+    ),
+    (
+        "installer.complete_no_runtime",
+        "INSTALACION COMPLETA. COPIADOS ARRANQUE + SISTEMA. LINUXRT NO INCLUIDO EN ESTA COMPILACION.",
+        "INSTALL COMPLETE. BOOT + SYSTEM COPIED. LINUXRT NOT EMBEDDED IN BUILD.",
+    ),
+    ("installer.grub_enabled", " MENU GRUB ACTIVADO.", " GRUB MENU ENABLED."),
+    (
+        "installer.sb_no_shim",
+        "SECURE BOOT ACTIVO Y ESTE MEDIO NO TRAE SHIM: EL SISTEMA INSTALADO NO ARRANCARA SIN DESACTIVARLO.",
+        "SECURE BOOT IS ON AND THIS MEDIA HAS NO SHIM: THE INSTALLED SYSTEM WILL NOT BOOT UNTIL IT IS DISABLED.",
+    ),
+    (
+        "installer.mok_title",
+        "SECURE BOOT: INSCRIPCION DE LA CLAVE DE ZENOX OS",
+        "SECURE BOOT: ENROLLING THE ZENOX OS KEY",
+    ),
+    (
+        "installer.mok_intro",
+        "LA CLAVE QUEDA PENDIENTE. EN EL PROXIMO ARRANQUE MOKMANAGER PEDIRA CONFIRMARLA:",
+        "THE KEY IS QUEUED. ON THE NEXT BOOT MOKMANAGER WILL ASK YOU TO CONFIRM IT:",
+    ),
+    (
+        "installer.mok_error",
+        "NO SE PUDO PREPARAR LA INSCRIPCION ({}). USA 'secureboot enroll' O mokutil --import REDUX.CER.",
+        "COULD NOT QUEUE THE ENROLLMENT ({}). USE 'secureboot enroll' OR mokutil --import REDUX.CER.",
+    ),
+    (
+        "installer.mok_continue",
+        "APUNTA LA CONTRASENA Y PULSA ENTER PARA CONTINUAR.",
+        "WRITE DOWN THE PASSWORD AND PRESS ENTER TO CONTINUE.",
+    ),
+    (
+        "installer.crypt_title",
+        "CIFRADO DE LA PARTICION DE DATOS",
+        "DATA PARTITION ENCRYPTION",
+    ),
+    (
+        "installer.crypt_intro",
+        "PUEDES CIFRAR ZENOX DATA CON AES-XTS. SE BORRA TODO SU CONTENIDO. ESC LA DEJA COMO ESTA.",
+        "YOU CAN ENCRYPT ZENOX DATA WITH AES-XTS. ALL ITS CONTENTS ARE ERASED. ESC LEAVES IT AS IT IS.",
+    ),
+    ("installer.crypt_password", "CONTRASENA:", "PASSWORD:"),
+    ("installer.crypt_confirm", "REPITELA:  ", "REPEAT IT:"),
+    (
+        "installer.crypt_tpm_on",
+        "F2  DESBLOQUEO AUTOMATICO CON TPM: SI",
+        "F2  AUTOMATIC TPM UNLOCK: YES",
+    ),
+    (
+        "installer.crypt_tpm_off",
+        "F2  DESBLOQUEO AUTOMATICO CON TPM: NO",
+        "F2  AUTOMATIC TPM UNLOCK: NO",
+    ),
+    (
+        "installer.crypt_keys",
+        "ENTER SIGUIENTE / CIFRAR   ESC OMITIR",
+        "ENTER NEXT / ENCRYPT   ESC SKIP",
+    ),
+    (
+        "installer.crypt_short",
+        "LA CONTRASENA NECESITA AL MENOS {} CARACTERES.",
+        "THE PASSWORD NEEDS AT LEAST {} CHARACTERS.",
+    ),
+    (
+        "installer.crypt_mismatch",
+        "LAS CONTRASENAS NO COINCIDEN. VUELVE A ESCRIBIRLA.",
+        "THE PASSWORDS DO NOT MATCH. TYPE IT AGAIN.",
+    ),
+    (
+        "installer.crypt_working",
+        "DERIVANDO LA CLAVE Y FORMATEANDO...",
+        "DERIVING THE KEY AND FORMATTING...",
+    ),
+    (
+        "installer.crypt_done",
+        "ZENOX DATA CIFRADA. SE PEDIRA LA CONTRASENA AL ARRANCAR.",
+        "ZENOX DATA ENCRYPTED. THE PASSWORD WILL BE ASKED FOR AT BOOT.",
+    ),
+    (
+        "installer.crypt_error",
+        "NO SE PUDO CIFRAR ZENOX DATA: {}",
+        "COULD NOT ENCRYPT ZENOX DATA: {}",
+    ),
+    (
+        "installer.crypt_continue",
+        "PULSA ENTER PARA CONTINUAR.",
+        "PRESS ENTER TO CONTINUE.",
+    ),
+    (
+        "installer.install_error",
+        "ERROR DE INSTALACION: {}",
+        "INSTALL ERROR: {}",
+    ),
+    // Start menu.
+    ("menu.search", "Buscar", "Search"),
+    ("menu.favorites", "Favoritos", "Favorites"),
+    ("menu.explorer", "Explorador", "File Explorer"),
+    ("menu.browser", "Navegador web", "Web Browser"),
+    ("menu.settings", "Configuracion", "Settings"),
+    ("menu.tools", "Herramientas >", "Tools >"),
+    ("menu.games", "Juegos >", "Games >"),
+    ("menu.apps", "Apps >", "Apps >"),
+    ("menu.suspend", "Suspender", "Suspend"),
+    ("menu.shutdown", "Apagar", "Shut down"),
+    ("menu.restart", "Reiniciar", "Restart"),
+    // Settings window.
+    ("settings.title", "Configuracion del Sistema", "System Settings"),
+    ("settings.software", "Informacion de Software:", "Software:"),
+    ("settings.memory", "Memoria RAM (Heap):", "Memory (heap):"),
+    ("settings.network", "Estado de Red:", "Network status:"),
+    (
+        "settings.registry",
+        "Registro de configuracion:",
+        "Configuration registry:",
+    ),
+    (
+        "settings.registry_keys",
+        "- {} claves, revision {} ({})",
+        "- {} keys, revision {} ({})",
+    ),
+    (
+        "settings.language",
+        "- Idioma: {} (lang es|en)",
+        "- Language: {} (lang es|en)",
+    ),
+    ("settings.memory_only", "solo memoria", "memory only"),
+    ("settings.scale", "- Escala de interfaz: {} (scale 1x|1.5x|2x)", "- UI scale: {} (scale 1x|1.5x|2x)"),
+    ("settings.display", "Pantalla:", "Display:"),
+    ("settings.gamma", "- Gamma: {}", "- Gamma: {}"),
+    ("settings.temperature", "- Temperatura de color: {} K", "- Color temperature: {} K"),
+    ("settings.night_light", "- Luz nocturna: {}", "- Night light: {}"),
+    ("settings.night_off", "desactivada", "off"),
+    ("settings.night_on", "siempre", "always"),
+    ("settings.night_schedule", "programada {}-{}", "scheduled {}-{}"),
+    ("settings.night_active", " (activa)", " (active)"),
+    ("settings.sound", "Sonido:", "Sound:"),
+    ("settings.sound_input", "- Entrada: {}", "- Input: {}"),
+    ("settings.sound_none", "- Entrada: ninguna", "- Input: none"),
+    ("settings.sound_level", "- Nivel de entrada: {} dB", "- Input level: {} dB"),
+    // Help headers.
+    ("help.shell_header", "Comandos:", "Commands:"),
+    ("help.desktop_header", "Comandos disponibles:", "Available commands:"),
+    // Command descriptions shared by the shell and desktop help.
+    ("help.help", "muestra esta ayuda", "show this help"),
+    ("help.about", "informacion del sistema", "system info"),
+    ("help.clear", "limpia la pantalla", "clear screen"),
+    ("help.mem", "estadisticas de memoria", "memory statistics"),
+    ("help.alloc", "reserva un frame de 4KiB", "allocate one 4KiB frame"),
+    ("help.idt", "informacion del esqueleto IDT", "IDT skeleton info"),
+    ("help.tick", "timer y tiempo encendido", "timer/uptime info"),
+    ("help.sched", "estadisticas del scheduler", "scheduler stats"),
+    (
+        "help.acpi",
+        "diagnostico de suspension ACPI S3",
+        "ACPI S3 suspend diagnostics",
+    ),
+    (
+        "help.suspend",
+        "suspende con ACPI S3 o s2idle y despierta con una tecla, la tapa o el boton de encendido",
+        "suspend with ACPI S3 or s2idle; a key, the lid or the power button wakes it",
+    ),
+    ("help.step", "ejecuta +100 ticks virtuales", "run +100 virtual ticks"),
+    (
+        "help.format",
+        "formatea el disco virtual como FAT32",
+        "format virtual disk as FAT32",
+    ),
+    (
+        "help.boot",
+        "sale de boot services y arranca el runtime IRQ-safe (con fallback)",
+        "exit boot services and start IRQ-safe runtime (auto fallback)",
+    ),
+    (
+        "help.boot_poll",
+        "fuerza el runtime estable por polling",
+        "force stable polling runtime",
+    ),
+    (
+        "help.boot_uefi",
+        "escritorio sin ExitBootServices (entrada UEFI: USB OK)",
+        "start GUI without ExitBootServices (UEFI input: USB OK)",
+    ),
+    (
+        "help.boot_irq",
+        "runtime experimental PIT/IRQ (con fallback); la shell corre en userspace via syscalls",
+        "start experimental PIT/IRQ runtime (auto fallback); the shell runs in user space via syscalls",
+    ),
+    ("help.echo", "imprime texto", "print text"),
+    ("help.panic", "prueba de panic", "panic test"),
+    ("help.reboot", "reinicia la maquina", "reboot VM"),
+    (
+        "help.gui",
+        "entra al escritorio de ventanas",
+        "enter windowed desktop mode",
+    ),
+    (
+        "help.installer",
+        "abre el instalador grafico preboot",
+        "open graphical pre-boot installer",
+    ),
+    ("help.ls", "lista archivos", "list files"),
+    ("help.cd", "cambia de directorio", "change directory"),
+    ("help.cat", "muestra un archivo", "read file"),
+    (
+        "help.cp",
+        "copia un archivo (rutas simples)",
+        "copy file (supports simple paths)",
+    ),
+    ("help.mv", "mueve o renombra un archivo", "move/rename file"),
+    (
+        "help.disks",
+        "lista dispositivos BlockIO UEFI (USB/NVMe/HDD)",
+        "list UEFI BlockIO devices (USB/NVMe/HDD)",
+    ),
+    (
+        "help.vols",
+        "lista volumenes FAT32/exFAT montables",
+        "list mountable FAT32/exFAT volumes",
+    ),
+    (
+        "help.df",
+        "espacio usado y libre del volumen FAT32 montado",
+        "used and free space on the mounted FAT32 volume",
+    ),
+    (
+        "help.mount",
+        "monta FAT32/exFAT por indice de 'disks'",
+        "mount FAT32/exFAT from 'disks' index",
+    ),
+    (
+        "help.mount_at",
+        "monta un volumen de 'disks' (/dev/nvme0p1) en una carpeta; 'host' y 'sys' tambien",
+        "mount a 'disks' volume (/dev/nvme0p1) on a folder; 'host' and 'sys' too",
+    ),
+    ("help.umount", "desmonta la carpeta de un montaje", "unmount the folder of a mount"),
+    (
+        "help.mounts",
+        "tabla de montajes: /, /host, /sys, /dev y los anadidos",
+        "mount table: /, /host, /sys, /dev and added mounts",
+    ),
+    ("help.unmount", "desmonta el volumen activo", "unmount active volume"),
+    (
+        "help.cpdev",
+        "copia un archivo entre dispositivos",
+        "copy file between devices",
+    ),
+    (
+        "help.cppdoom",
+        "abre la app nativa CPP-DOOM",
+        "launch CPP-DOOM native app",
+    ),
+    (
+        "help.shell",
+        "carga la UEFI Shell externa (SHELLX64.EFI)",
+        "chainload external UEFI Shell image (SHELLX64.EFI)",
+    ),
+    (
+        "help.linux_guest",
+        "carga el loader EFI de Linux guest (ruta 2: compat Linux real)",
+        "chainload Linux guest EFI loader (route 2: real Linux compat)",
+    ),
+    (
+        "help.net",
+        "estado de transporte/IP/failover",
+        "show transport/IP/failover status",
+    ),
+    (
+        "help.net_dhcp",
+        "pasa a DHCP y pide IP dinamica",
+        "switch to DHCP and request a dynamic IP",
+    ),
+    (
+        "help.net_static",
+        "aplica el perfil de IP fija por defecto",
+        "apply default static IP profile",
+    ),
+    (
+        "help.net_static_custom",
+        "aplica una IP fija propia",
+        "apply custom static IP",
+    ),
+    ("help.net_mode", "muestra el modo IP actual", "show current IP mode"),
+    (
+        "help.net_https",
+        "modo de compatibilidad HTTPS",
+        "HTTPS compatibility mode",
+    ),
+    (
+        "help.net_diag",
+        "vuelca registros RX/TX de Intel Ethernet",
+        "dump Intel Ethernet RX/TX registers",
+    ),
+    (
+        "help.net_bench",
+        "mide el throughput de descarga HTTP",
+        "measure HTTP download throughput",
+    ),
+    (
+        "help.net_pool",
+        "conexiones HTTP reutilizables por origen",
+        "reusable HTTP connections per origin",
+    ),
+    (
+        "help.net_jobs",
+        "peticiones HTTP en segundo plano",
+        "background HTTP requests",
+    ),
+    (
+        "help.net_profile",
+        "perfiles de red por interfaz (DHCP/IP fija, DNS, proxy)",
+        "per-interface network profiles (DHCP/static, DNS, proxy)",
+    ),
+    (
+        "help.net_portal",
+        "deteccion de portal cautivo y pagina de acceso",
+        "captive portal detection and login page",
+    ),
+    (
+        "help.net_stats",
+        "trafico por interfaz y por programa",
+        "traffic per interface and per program",
+    ),
+    (
+        "help.net_arp",
+        "tabla ARP/NDP y entradas estaticas",
+        "ARP/NDP table and static entries",
+    ),
+    (
+        "help.net_mtu",
+        "MTU por interfaz y tramas jumbo",
+        "per-interface MTU and jumbo frames",
+    ),
+    (
+        "help.wifi",
+        "estado del driver WiFi Intel",
+        "show Intel WiFi driver status",
+    ),
+    ("help.wifi_scan", "busca redes WiFi", "scan WiFi networks"),
+    (
+        "help.wifi_connect",
+        "guarda el perfil y conecta",
+        "save profile and connect",
+    ),
+    ("help.wifi_disconnect", "desconecta el WiFi", "disconnect WiFi"),
+    (
+        "help.wifi_failover",
+        "prioridad automatica de transporte",
+        "set automatic transport priority",
+    ),
+    (
+        "help.fetch",
+        "descarga un archivo de la red",
+        "download file from network",
+    ),
+    ("help.web_backend", "motor de render del navegador", "browser renderer"),
+    (
+        "help.web_litehtmlrt",
+        "runtime LinuxRT para litehtml",
+        "LinuxRT runtime for litehtml",
+    ),
+    (
+        "help.web_servort",
+        "runtime LinuxRT para Servo",
+        "LinuxRT runtime for Servo",
+    ),
+    (
+        "help.web_vaev",
+        "diagnostico del puente Vaev integrado",
+        "embedded Vaev bridge diagnostics",
+    ),
+    (
+        "help.web_native",
+        "motor nativo DOM/layout/raster",
+        "native DOM/layout/raster engine",
+    ),
+    ("help.web_webkit", "puente WebKit del host", "host WebKit bridge"),
+    (
+        "help.web_servohost",
+        "puente Servo del host (alias)",
+        "host Servo bridge (alias)",
+    ),
+    ("help.alias_webkit", "alias de web webkit", "alias for web webkit"),
+    (
+        "help.alias_servohost",
+        "alias de web servohost",
+        "alias for web servohost",
+    ),
+    ("help.alias_servort", "alias de web servort", "alias for web servort"),
+    (
+        "help.log",
+        "buffer circular del log del kernel",
+        "kernel log ring buffer",
+    ),
+    (
+        "help.log_save",
+        "archiva el log en LOGS/KLOGnnnn.GZ",
+        "archive the log ring to LOGS/KLOGnnnn.GZ",
+    ),
+    (
+        "help.log_file",
+        "estado de \\REDUXOS\\LOGS\\SYSTEM.LOG (se escribe cada 2 s)",
+        "status of \\REDUXOS\\LOGS\\SYSTEM.LOG (written every 2 s)",
+    ),
+    (
+        "help.log_remote",
+        "reenvio del log a un colector syslog",
+        "forward the kernel log to a syslog collector",
+    ),
+    ("help.hwinfo", "inventario de hardware", "hardware inventory"),
+    (
+        "help.cryptobench",
+        "velocidad de AES-GCM y SHA-256 por hardware y por software",
+        "AES-GCM and SHA-256 speed in hardware and software",
+    ),
+    (
+        "help.bench",
+        "pruebas de rendimiento fijas de memoria, disco, red y escritorio; --json guarda los resultados",
+        "fixed memory, disk, network and desktop benchmarks; --json saves the results",
+    ),
+    (
+        "help.secureboot",
+        "estado de Secure Boot y shim; enroll prepara la clave para MokManager, cancel la retira",
+        "Secure Boot and shim state; enroll queues the key for MokManager, cancel drops it",
+    ),
+    (
+        "crypt.title",
+        "ZENOX OS: PARTICION DE DATOS CIFRADA",
+        "ZENOX OS: ENCRYPTED DATA PARTITION",
+    ),
+    (
+        "crypt.prompt",
+        "CONTRASENA PARA ABRIR {} PARTICION(ES) CIFRADA(S):",
+        "PASSWORD TO OPEN {} ENCRYPTED PARTITION(S):",
+    ),
+    (
+        "crypt.hint",
+        "ENTER DESBLOQUEA   ESC ARRANCA SIN LA PARTICION DE DATOS",
+        "ENTER UNLOCKS   ESC BOOTS WITHOUT THE DATA PARTITION",
+    ),
+    ("crypt.deriving", "COMPROBANDO...", "CHECKING..."),
+    ("crypt.wrong", "CONTRASENA INCORRECTA.", "WRONG PASSWORD."),
+    (
+        "help.bootvar",
+        "Entradas de arranque UEFI: ordenar, activar, borrar, BootNext y limpiar duplicadas; -n simula y cada cambio guarda una copia para restore",
+        "UEFI boot entries: reorder, enable, delete, BootNext and clean up duplicates; -n is a dry run and every change saves a copy for restore",
+    ),
+    (
+        "help.osprober",
+        "Sistemas en otros volumenes (Linux, Windows) para el menu de GRUB; update regenera los grub.cfg de Zenox",
+        "Systems on other volumes (Linux, Windows) for the GRUB menu; update regenerates Zenox's grub.cfg files",
+    ),
+    (
+        "help.linuxboot",
+        "Arranca un vmlinuz con su initrd (LoadFile2) sin cargador EFI; cmdline fija la linea de comandos (linux.cmdline)",
+        "Boot a vmlinuz with its initrd (LoadFile2) without an EFI loader; cmdline sets the command line (linux.cmdline)",
+    ),
+    (
+        "help.crypt",
+        "Particion de datos cifrada (AES-XTS); unlock abre con contrasena, o con el TPM si no se da",
+        "Encrypted data partition (AES-XTS); unlock opens it with a password, or with the TPM if none is given",
+    ),
+    (
+        "help.tpm",
+        "TPM 2.0, PCR sellados para el disco de datos; pcrs muestra el banco SHA-256 y log las medidas",
+        "TPM 2.0 and the PCRs the data disk is sealed to; pcrs shows the SHA-256 bank and log the measurements",
+    ),
+    (
+        "help.random",
+        "fuentes de entropia del generador o bytes aleatorios en hex",
+        "random generator entropy sources or random bytes in hex",
+    ),
+    (
+        "help.lspci",
+        "dispositivos PCI y drivers",
+        "PCI devices and bound drivers",
+    ),
+    (
+        "help.devices",
+        "arbol de dispositivos con clase, driver y atributos (tambien en /sys)",
+        "device tree with class, driver and attributes (also under /sys)",
+    ),
+    (
+        "help.mod",
+        "modulos del kernel (.rko, ELF reubicable) con drivers de bloques, red o entrada, sin recompilar",
+        "kernel modules (.rko, relocatable ELF) with block, net or input drivers, no rebuild needed",
+    ),
+    (
+        "help.host",
+        "carpeta compartida /host (virtio-9p)",
+        "virtio-9p shared folder at /host",
+    ),
+    (
+        "help.selftest",
+        "pruebas internas del kernel (feature selftest)",
+        "in-kernel tests (feature selftest)",
+    ),
+    ("help.firewall", "firewall HTTP de userspace", "userspace HTTP firewall"),
+    (
+        "help.gemini",
+        "certificados Gemini fijados (TOFU)",
+        "Gemini TOFU certificate pins",
+    ),
+    (
+        "help.ftp",
+        "cliente FTP/FTPS (modo pasivo)",
+        "FTP/FTPS client (passive mode)",
+    ),
+    ("help.mail", "correo IMAP/SMTP", "IMAP/SMTP mail client"),
+    (
+        "help.gzip",
+        "comprimir/descomprimir archivos",
+        "compress/decompress files",
+    ),
+    ("help.tar", "archivos tar", "tar archives"),
+    ("help.zip", "archivos zip", "zip archives"),
+    ("help.pkg", "gestor de paquetes", "package manager"),
+    ("help.notify", "notificaciones del escritorio", "desktop notifications"),
+    (
+        "help.config",
+        "registro de configuracion",
+        "system configuration registry",
+    ),
+    ("help.lang", "idioma de la interfaz", "interface language"),
+    ("help.scale", "escala de la interfaz para pantallas HiDPI", "UI scale for HiDPI screens"),
+    (
+        "help.effects",
+        "sombras, opacidad por ventana y animaciones (auto: solo con backbuffer)",
+        "shadows, per-window opacity and animations (auto: only with the backbuffer)",
+    ),
+    (
+        "help.workspace",
+        "escritorios con sus ventanas, cambiar (Super+N) o mover ventanas (Super+Shift+N)",
+        "workspaces and their windows, switch (Super+N) or move windows (Super+Shift+N)",
+    ),
+    (
+        "help.session",
+        "ventanas guardadas al apagar y reabiertas al arrancar (ask, always o never)",
+        "windows saved at shutdown and reopened at boot (ask, always or never)",
+    ),
+    (
+        "help.mic",
+        "microfono: nivel de entrada y grabadora de voz (WAV en \\RECORDS)",
+        "microphone: input level and voice recorder (WAV in \\RECORDS)",
+    ),
+    (
+        "help.sound",
+        "sonidos del sistema, notas y archivos MIDI por HDA o el altavoz del PC",
+        "system sounds, notes and MIDI files through HDA or the PC speaker",
+    ),
+    (
+        "help.remote",
+        "vista remota del escritorio: servidor VNC y captura por HTTP (/screenshot)",
+        "remote view of the desktop: VNC server and HTTP screenshot (/screenshot)",
+    ),
+    (
+        "help.usb",
+        "dispositivos USB conectados, puertos xHCI y nueva revision del bus",
+        "connected USB devices, xHCI ports and bus rescan",
+    ),
+    (
+        "help.thunderbolt",
+        "dispositivos PCIe por Thunderbolt/USB4: autorizar, denegar y nivel de seguridad",
+        "PCIe devices over Thunderbolt/USB4: authorize, deny and security level",
+    ),
+    (
+        "help.power",
+        "modo de suspension, accion de la tapa y drivers con suspend/resume",
+        "sleep mode, lid action and drivers with suspend/resume hooks",
+    ),
+    (
+        "help.cmdline",
+        "opciones de arranque de CMDLINE.TXT y de la entrada UEFI",
+        "boot options from CMDLINE.TXT and the UEFI entry",
+    ),
+    ("help.gamepad", "mandos USB (HID/XInput): estado, prueba y teclas asignadas", "USB gamepads (HID/XInput): status, test and key mapping"),
+    ("help.perf", "HUD de rendimiento (F12) y limite de fps del escritorio", "performance HUD (F12) and desktop frame cap"),
+    ("help.boottime", "tiempo de cada etapa del arranque y esperas diferidas", "time of each boot stage and deferred waits"),
+    (
+        "help.jobs",
+        "programas lanzados: directorio de trabajo, stdin/stdout/stderr y codigo de salida",
+        "launched programs: working directory, stdin/stdout/stderr and exit status",
+    ),
+    (
+        "help.touch",
+        "pantallas tactiles USB: dispositivos, reescaneo y orientacion del panel",
+        "USB touch screens: devices, rescan and panel orientation",
+    ),
+    (
+        "help.gamma",
+        "color de pantalla: gamma, temperatura de color y luz nocturna programada",
+        "display color: gamma, color temperature and scheduled night light",
+    ),
+    (
+        "help.print",
+        "impresoras IPP de la red: descubrir por mDNS, estado, trabajos y pagina de prueba",
+        "network IPP printers: mDNS discovery, status, jobs and a test page",
+    ),
+    (
+        "help.pdf",
+        "documentos PDF: informacion, texto de una pagina y visor con zoom y paginas",
+        "PDF documents: info, page text and a viewer with zoom and paging",
+    ),
+    (
+        "help.calc",
+        "calculadora: evalua una expresion o abre la Calculadora sin argumentos",
+        "calculator: evaluates an expression, or opens the Calculator with no arguments",
+    ),
+    (
+        "help.cal",
+        "calendario del mes con eventos locales: anadir, borrar y listar por dia o mes",
+        "month calendar with local events: add, delete and list by day or month",
+    ),
+    (
+        "help.alarm",
+        "alarmas y temporizadores que avisan con una notificacion; 'gui' abre el Reloj",
+        "alarms and timers that ring with a notification; 'gui' opens the Clock",
+    ),
+    (
+        "help.index",
+        "indice de archivos para la busqueda rapida (Super+Espacio): estado, reconstruir y buscar",
+        "file index behind quick search (Super+Space): status, rebuild and search",
+    ),
+    (
+        "help.fswatch",
+        "vigilancias de archivos abiertas y ultimos cambios notificados",
+        "open file watches and the latest reported changes",
+    ),
+    (
+        "help.ln",
+        "crea un enlace simbolico (guardado en los metadatos del volumen)",
+        "create a symbolic link (kept in the volume metadata)",
+    ),
+    (
+        "help.readlink",
+        "muestra a donde apunta un enlace simbolico",
+        "show where a symbolic link points",
+    ),
+    (
+        "help.xattr",
+        "lista, lee, fija o borra atributos extendidos de un archivo",
+        "list, read, set or remove a file's extended attributes",
+    ),
+    (
+        "help.chmod",
+        "cambia el modo octal de un archivo o carpeta (dueno o root)",
+        "change the octal mode of a file or folder (owner or root)",
+    ),
+    (
+        "help.chown",
+        "cambia el dueno y/o el grupo de un archivo o carpeta (solo root)",
+        "change the owner and/or group of a file or folder (root only)",
+    ),
+    ("help.stat", "muestra permisos, dueno y grupo", "show permissions, owner and group"),
+    (
+        "help.su",
+        "cambia el usuario de la sesion (root sin argumento)",
+        "switch the session user (root without argument)",
+    ),
+    ("help.id", "muestra el usuario y grupo de la sesion", "show the session user and group"),
+    (
+        "help.chkdsk",
+        "comprueba las cadenas de clusters FAT32; con reparar las corrige (root)",
+        "check FAT32 cluster chains; with reparar, fix them (root)",
+    ),
+    ("help.parts", "tablas de particiones de los discos internos", "partition tables of the internal disks"),
+    (
+        "help.uninstall",
+        "quita una instalacion de Zenox OS del arranque (modo de recuperacion)",
+        "remove a Zenox OS install from the boot menu (recovery mode)",
+    ),
+    (
+        "help.heapcheck",
+        "revisa los canarios del heap y muestra los fallos (kernels con heapcheck)",
+        "check heap canaries and show the faults (heapcheck kernels)",
+    ),
+    (
+        "help.replay",
+        "graba la entrada y la red de la proxima sesion o la reproduce",
+        "record the next session's input and network, or replay it",
+    ),
+    (
+        "help.osk",
+        "teclado en pantalla para tablets: modo, mostrar/ocultar y distribucion ES/EN",
+        "on-screen keyboard for tablets: mode, show/hide and ES/EN layout",
+    ),
+    (
+        "help.async",
+        "tareas async del kernel (peticiones de red como futures) y sus contadores",
+        "kernel async tasks (network requests as futures) and their counters",
+    ),
+    (
+        "help.clocksource",
+        "fuente de reloj elegida (TSC, HPET, ACPI PM) y su calibracion",
+        "selected clock source (TSC, HPET, ACPI PM) and its calibration",
+    ),
+    ("help.trace", "trazas de arranque, instalador, frames y red; save exporta JSON para chrome://tracing", "boot, installer, frame and network traces; save exports JSON for chrome://tracing"),
+    (
+        "help.checkpoint",
+        "guarda el proceso Linux de un hilo (registros, memoria, descriptores) y lo rebobina en la misma sesion",
+        "save the single-threaded Linux process (registers, memory, descriptors) and rewind it in the same session",
+    ),
+    ("help.screenshot", "captura la pantalla como BMP y pregunta donde guardarla", "capture the screen as BMP and ask where to save it"),
+    (
+        "help.stream",
+        "scheduler de salida multitarea para terminal/procesos",
+        "multitask output scheduler for terminal/processes",
+    ),
+    (
+        "help.tasks",
+        "cola/throttle de tareas de sistema (cp/mv/cpdev en background)",
+        "system task queue/throttle (background cp/mv/cpdev)",
+    ),
+    ("help.install", "instala un paquete", "install package"),
+    (
+        "help.entry",
+        "punto de entrada generico del instalador",
+        "generic installer entry point",
+    ),
+    (
+        "help.newlib",
+        "scripts/newlib_port.sh (scaffold/build/doctor)",
+        "scripts/newlib_port.sh (scaffold/build/doctor)",
+    ),
+    ("help.ruby", "runtime del subconjunto de Ruby", "Ruby subset runtime"),
+    (
+        "help.runapp",
+        "abre una app .RML en App Runner",
+        "open .RML app in App Runner",
+    ),
+    (
+        "help.ide",
+        "abre Redux Studio (editor + preview + install/export .rpx)",
+        "open Redux Studio (editor + preview + install/export .rpx)",
+    ),
+    // `lang` command.
+    ("lang.current", "Idioma: {} ({})", "Language: {} ({})"),
+    (
+        "lang.available",
+        "Disponibles: es, en; otros codigos con \\REDUXOS\\LANG\\<CODIGO>.TXT",
+        "Available: es, en; other codes via \\REDUXOS\\LANG\\<CODE>.TXT",
+    ),
+    ("lang.set", "Idioma cambiado a {}.", "Language changed to {}."),
+    (
+        "lang.invalid",
+        "Codigo de idioma invalido: {}",
+        "Invalid language code: {}",
+    ),
+    ("lang.pack", "paquete {} con {} textos", "pack {} with {} texts"),
+    ("lang.builtin", "catalogo integrado", "built-in catalog"),
+    ("lang.format", "Formato: {} | {} | {}", "Format: {} | {} | {}"),
+    // `df` command.
+    (
+        "df.no_volume",
+        "Sistema de archivos no disponible. Usa 'disks' y luego 'mount <n>'.",
+        "Filesystem not available. Use 'disks' and then 'mount <n>'.",
+    ),
+    (
+        "df.fat32_only",
+        "df: espacio libre solo disponible en FAT32.",
+        "df: free space is only available on FAT32.",
+    ),
+    (
+        "df.summary",
+        "{}: {} MiB en total, {} MiB usados, {} MiB libres ({} clusters de {} bytes libres)",
+        "{}: {} MiB total, {} MiB used, {} MiB free ({} free clusters of {} bytes)",
+    ),
+];
@@ -0,0 +1,214 @@
+//! Numbers, sizes and dates written the way the selected language does.
+//!
+//! The separators and the date order come from the language part of
+//! `system.locale` ("pt" in "pt-br"), with the region only where it changes
+//! the date ("en" is month first, "en-gb" day first). Unknown codes get ISO
+//! dates and English separators. The free functions use the current locale;
+//! [`Style`] is there for callers and tests that pick one.
+
+use alloc::string::String;
+
+use crate::calendar::Date;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DateOrder {
+    DayMonthYear,
+    MonthDayYear,
+    YearMonthDay,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Style {
+    /// Between groups of three digits.
+    pub group: char,
+    pub decimal: char,
+    pub order: DateOrder,
+    pub date_sep: char,
+}
+
+const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+impl Style {
+    pub const ISO: Style = Style {
+        group: ',',
+        decimal: '.',
+        order: DateOrder::YearMonthDay,
+        date_sep: '-',
+    };
+
+    pub fn for_code(code: &str) -> Self {
+        let (lang, region) = code.split_once('-').unwrap_or((code, ""));
+        let style = |group, decimal, order, date_sep| Style {
+            group,
+            decimal,
+            order,
+            date_sep,
+        };
+        match lang {
+            "en" if region.is_empty() || region == "us" => style(',', '.', DateOrder::MonthDayYear, '/'),
+            "en" => style(',', '.', DateOrder::DayMonthYear, '/'),
+            "es" | "it" | "pt" | "nl" | "id" | "tr" | "da" | "el" => style('.', ',', DateOrder::DayMonthYear, '/'),
+            "de" => style('.', ',', DateOrder::DayMonthYear, '.'),
+            "fr" => style(' ', ',', DateOrder::DayMonthYear, '/'),
+            "ru" | "pl" | "cs" | "sk" | "fi" | "nb" | "uk" => style(' ', ',', DateOrder::DayMonthYear, '.'),
+            "sv" | "lt" => style(' ', ',', DateOrder::YearMonthDay, '-'),
+            "ja" | "zh" | "ko" => style(',', '.', DateOrder::YearMonthDay, '/'),
+            _ => Style::ISO,
+        }
+    }
+
+    pub fn current() -> Self {
+        Self::for_code(super::current_code().as_str())
+    }
+
+    /// `n` with thousands separators.
+    pub fn integer(self, n: u64) -> String {
+        let digits = alloc::format!("{}", n);
+        let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                out.push(self.group);
+            }
+            out.push(c);
+        }
+        out
+    }
+
+    pub fn signed(self, n: i64) -> String {
+        let text = self.integer(n.unsigned_abs());
+        if n < 0 {
+            alloc::format!("-{}", text)
+        } else {
+            text
+        }
+    }
+
+    /// `value / 10^decimals`, truncated.
+    pub fn fixed(self, value: u64, decimals: u32) -> String {
+        if decimals == 0 {
+            return self.integer(value);
+        }
+        let scale = 10u64.pow(decimals);
+        alloc::format!(
+            "{}{}{:0width$}",
+            self.integer(value / scale),
+            self.decimal,
+            value % scale,
+            width = decimals as usize
+        )
+    }
+
+    /// Bytes in the largest binary unit that keeps the number at least 1,
+    /// with one decimal below 100 ("1,5 MiB", "512 B", "120 GiB").
+    pub fn size(self, bytes: u64) -> String {
+        if bytes < 1024 {
+            return alloc::format!("{} B", bytes);
+        }
+        let mut unit = 0;
+        let mut scale = 1024u64;
+        while unit + 1 < UNITS.len() && bytes / scale >= 1024 {
+            unit += 1;
+            scale *= 1024;
+        }
+        let tenths = (bytes as u128 * 10 / scale as u128) as u64;
+        if tenths >= 1000 {
+            alloc::format!("{} {}", self.integer(tenths / 10), UNITS[unit])
+        } else {
+            alloc::format!("{} {}", self.fixed(tenths, 1), UNITS[unit])
+        }
+    }
+
+    /// Whole mebibytes, for memory totals ("1.024 MiB").
+    pub fn mib(self, bytes: u64) -> String {
+        alloc::format!("{} MiB", self.integer(bytes / (1024 * 1024)))
+    }
+
+    pub fn date(self, date: Date) -> String {
+        let sep = self.date_sep;
+        match self.order {
+            DateOrder::DayMonthYear => alloc::format!("{:02}{}{:02}{}{:04}", date.day, sep, date.month, sep, date.year),
+            DateOrder::MonthDayYear => alloc::format!("{:02}{}{:02}{}{:04}", date.month, sep, date.day, sep, date.year),
+            DateOrder::YearMonthDay => alloc::format!("{:04}{}{:02}{}{:02}", date.year, sep, date.month, sep, date.day),
+        }
+    }
+
+    /// Date and `HH:MM`; `minute` counts from midnight.
+    pub fn date_time(self, date: Date, minute: u16) -> String {
+        alloc::format!("{} {}", self.date(date), crate::calendar::hhmm(minute))
+    }
+
+    /// FAT directory entry date and time, or `None` when the date is unset.
+    pub fn fat_date_time(self, date: u16, time: u16) -> Option<String> {
+        let date = Date::new(
+            1980 + (date >> 9) as i32,
+            ((date >> 5) & 0x0F) as u8,
+            (date & 0x1F) as u8,
+        )?;
+        let minute = (time >> 11).min(23) * 60 + ((time >> 5) & 0x3F).min(59);
+        Some(self.date_time(date, minute))
+    }
+}
+
+pub fn integer(n: u64) -> String {
+    Style::current().integer(n)
+}
+
+pub fn size(bytes: u64) -> String {
+    Style::current().size(bytes)
+}
+
+pub fn mib(bytes: u64) -> String {
+    Style::current().mib(bytes)
+}
+
+pub fn date(date: Date) -> String {
+    Style::current().date(date)
+}
+
+pub fn fat_date_time(date: u16, time: u16) -> Option<String> {
+    Style::current().fat_date_time(date, time)
+}
+
+/// The wall clock in local time.
+pub fn now() -> String {
+    let (date, minute) = crate::calendar::local_now();
+    Style::current().date_time(date, minute)
+}
+
+crate::selftest::kernel_tests! {
+    "i18n_format";
+
+    fn numbers_follow_the_language() {
+        let es = Style::for_code("es");
+        let en = Style::for_code("en");
+        crate::selftest::ensure_eq(es.integer(1_234_567).as_str(), "1.234.567", "es")?;
+        crate::selftest::ensure_eq(en.integer(1_234_567).as_str(), "1,234,567", "en")?;
+        crate::selftest::ensure_eq(es.integer(999).as_str(), "999", "sin grupo")?;
+        crate::selftest::ensure_eq(Style::for_code("fr").signed(-12_000).as_str(), "-12 000", "fr")?;
+        crate::selftest::ensure_eq(es.fixed(1205, 2).as_str(), "12,05", "decimales")?;
+        crate::selftest::ensure_eq(Style::for_code("pt-br"), es, "region sin cambio")
+    }
+
+    fn sizes_pick_a_binary_unit() {
+        let es = Style::for_code("es");
+        crate::selftest::ensure_eq(es.size(512).as_str(), "512 B", "bytes")?;
+        crate::selftest::ensure_eq(es.size(1536).as_str(), "1,5 KiB", "KiB")?;
+        crate::selftest::ensure_eq(es.size(1024 * 1024 - 1).as_str(), "1.023 KiB", "sin decimal")?;
+        crate::selftest::ensure_eq(es.size(3 << 30).as_str(), "3,0 GiB", "GiB")?;
+        crate::selftest::ensure_eq(es.size(2048u64 << 40).as_str(), "2.048 TiB", "tope TiB")?;
+        crate::selftest::ensure_eq(Style::for_code("en").mib(1 << 30).as_str(), "1,024 MiB", "MiB")
+    }
+
+    fn dates_follow_the_region() {
+        let date = Date::new(2026, 3, 9).ok_or_else(|| String::from("fecha"))?;
+        crate::selftest::ensure_eq(Style::for_code("es").date(date).as_str(), "09/03/2026", "es")?;
+        crate::selftest::ensure_eq(Style::for_code("en").date(date).as_str(), "03/09/2026", "en")?;
+        crate::selftest::ensure_eq(Style::for_code("en-gb").date(date).as_str(), "09/03/2026", "en-gb")?;
+        crate::selftest::ensure_eq(Style::for_code("de").date(date).as_str(), "09.03.2026", "de")?;
+        crate::selftest::ensure_eq(Style::for_code("xx").date(date).as_str(), "2026-03-09", "iso")?;
+        // 2026-03-09 14:05 in FAT encoding.
+        let fat = Style::ISO.fat_date_time((46 << 9) | (3 << 5) | 9, (14 << 11) | (5 << 5));
+        crate::selftest::ensure_eq(fat.as_deref(), Some("2026-03-09 14:05"), "FAT")?;
+        crate::selftest::ensure_eq(Style::ISO.fat_date_time(0, 0), None, "sin fecha")
+    }
+}
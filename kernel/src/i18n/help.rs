@@ -0,0 +1,337 @@
+//! Command help for the text shell and the desktop terminal. Usage strings
+//! stay as typed; descriptions come from the catalog.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// `(usage, description key)` for the text shell.
+pub(super) const SHELL_HELP: &[(&str, &str)] = &[
+    ("help", "help.help"),
+    ("about", "help.about"),
+    ("clear", "help.clear"),
+    ("mem", "help.mem"),
+    ("alloc", "help.alloc"),
+    ("idt", "help.idt"),
+    ("tick", "help.tick"),
+    ("sched", "help.sched"),
+    ("acpi", "help.acpi"),
+    ("suspend [auto|s3|s2idle]", "help.suspend"),
+    ("step", "help.step"),
+    ("format", "help.format"),
+    ("boot", "help.boot"),
+    ("boot poll", "help.boot_poll"),
+    ("boot uefi", "help.boot_uefi"),
+    ("boot irq", "help.boot_irq"),
+    ("echo <text>", "help.echo"),
+    ("panic", "help.panic"),
+    ("reboot", "help.reboot"),
+    ("gui", "help.gui"),
+    ("installer", "help.installer"),
+    ("disks", "help.disks"),
+    ("vols", "help.vols"),
+    ("mount <n>", "help.mount"),
+    ("mount <dispositivo> <dir>", "help.mount_at"),
+    ("umount <dir>", "help.umount"),
+    ("mounts", "help.mounts"),
+    ("df", "help.df"),
+    ("cppdoom", "help.cppdoom"),
+    ("shell", "help.shell"),
+    ("linux guest", "help.linux_guest"),
+    ("net", "help.net"),
+    ("net dhcp", "help.net_dhcp"),
+    ("net static", "help.net_static"),
+    ("net static <ip> <prefix> <gateway>", "help.net_static_custom"),
+    ("net mode", "help.net_mode"),
+    ("net https <on|off|status>", "help.net_https"),
+    ("net diag", "help.net_diag"),
+    ("net bench [url]", "help.net_bench"),
+    ("net pool [clear]", "help.net_pool"),
+    ("net jobs [get <url>|cancel <id>]", "help.net_jobs"),
+    ("net profile [list|save <nombre>|load <nombre>|delete <nombre>]", "help.net_profile"),
+    ("net portal [check|open]", "help.net_portal"),
+    ("net stats", "help.net_stats"),
+    ("net arp [list|flush|add <ip> <mac>|del <ip>]", "help.net_arp"),
+    ("net mtu [[intel|virtio] <bytes>|default]", "help.net_mtu"),
+    ("wifi", "help.wifi"),
+    ("wifi scan", "help.wifi_scan"),
+    ("wifi connect <ssid> <key>", "help.wifi_connect"),
+    ("wifi disconnect", "help.wifi_disconnect"),
+    ("wifi failover <ethernet|wifi|status>", "help.wifi_failover"),
+    ("log [tail <n>] | dmesg", "help.log"),
+    ("log save", "help.log_save"),
+    ("log file", "help.log_file"),
+    (
+        "log remote <ip[:port]> [udp|tcp] | log remote <off|status|level <lvl>>",
+        "help.log_remote",
+    ),
+    ("hwinfo [cpu|mem|pci|disk|display|net|input]", "help.hwinfo"),
+    ("cryptobench [KiB]", "help.cryptobench"),
+    ("bench [mem|fs|net|gui|all] [--json]", "help.bench"),
+    ("random [status|<bytes>]", "help.random"),
+    ("secureboot [status|enroll|cancel]", "help.secureboot"),
+    ("tpm [status|pcrs|log]", "help.tpm"),
+    ("crypt [status|unlock [contrasena]]", "help.crypt"),
+    ("bootvar [list|order|enable|disable|delete|next|clean|restore|gui] [-n]", "help.bootvar"),
+    ("osprober [list|cfg|update]", "help.osprober"),
+    ("linuxboot [list|boot <n> [linea]|cmdline [texto]]", "help.linuxboot"),
+    ("lspci [-v] | lspci rescan | lspci drivers", "help.lspci"),
+    ("devices [tree|class [name]|show <device>]", "help.devices"),
+    ("mod [list|load <file.rko>|unload <name>|dev]", "help.mod"),
+    ("host [status|ls|cat|get|put|mkdir|rm]", "help.host"),
+    ("selftest [list|<suite>[::test]]", "help.selftest"),
+    (
+        "firewall [list|allow|deny <host>[:port]|rm <n>|policy allow|deny|check]",
+        "help.firewall",
+    ),
+    ("gemini [pins|forget <host>]", "help.gemini"),
+    ("ftp [status|open <ftp[s]://host>|ls|cd|pwd|get|put|close]", "help.ftp"),
+    (
+        "mail [status|setup <email> <password>|set|show|inbox [n]|read <n>|send <to> <file>]",
+        "help.mail",
+    ),
+    ("gzip [-1..-9] <file> [out] | gunzip <file.gz> [out]", "help.gzip"),
+    (
+        "tar xf|tf <file.tar[.gz]> [dir] | tar cf|czf <file> <path>...",
+        "help.tar",
+    ),
+    ("unzip [-l] <file.zip> [dir] | zip <file.zip> <path>...", "help.zip"),
+    (
+        "pkg [repo <url>|update|search|info|list|install <pkg>...|remove <pkg>|autoremove|upgrade]",
+        "help.pkg",
+    ),
+    ("notify [list|clear|send|warn|error <title> [| text]]", "help.notify"),
+    (
+        "config [list [prefix]|get <key>|set <key> <value>|unset <key>|save]",
+        "help.config",
+    ),
+    ("lang [es|en|<code>]", "help.lang"),
+    ("scale [1x|1.5x|2x]", "help.scale"),
+    ("effects [on|off|auto] | effects opacity <20-100> [title]", "help.effects"),
+    ("workspace [n] | workspace move <n> [title]", "help.workspace"),
+    ("session [status|save|load|clear|restore ask|always|never]", "help.session"),
+    ("mic [status|rec|stop|gui]", "help.mic"),
+    (
+        "sound [status|list|play <name>|beep [hz] [ms]|note <n> [ms] [program]|midi <file>|on|off|output auto|speaker]",
+        "help.sound",
+    ),
+    ("remote [status|vnc on|off|auto|http on|off|auto]", "help.remote"),
+    ("usb [list|rescan]", "help.usb"),
+    (
+        "thunderbolt [status|authorize <n>|deny <n>|security none|user|secure|dponly|forget]",
+        "help.thunderbolt",
+    ),
+    ("power [status|mode auto|s3|s2idle|lid suspend|ignore|acpi]", "help.power"),
+    ("cmdline", "help.cmdline"),
+    ("gamepad [list|rescan|test|map [<button> <key>]|deadzone <n>]", "help.gamepad"),
+    ("perf [status|hud [on|off]|cap <fps>|off|auto]", "help.perf"),
+    ("trace [status|on|off|clear|save [file]]", "help.trace"),
+    ("checkpoint [save|restore|info] [name]", "help.checkpoint"),
+    ("boottime", "help.boottime"),
+    ("clocksource", "help.clocksource"),
+    ("async", "help.async"),
+    ("jobs", "help.jobs"),
+    ("touch [list|rescan|orientation <normal|left|right|inverted>]", "help.touch"),
+    ("gamma [<0.5-3.0>|temp <K>|night off|on|schedule [HH:MM HH:MM]|backend auto|hw|sw]", "help.gamma"),
+    ("print [list|discover|add <uri> [nombre]|default <n>|info [n]|jobs|cancel <id>|test [n]]", "help.print"),
+    ("pdf [info <archivo>|text <archivo> [pagina]|open <archivo|url>]", "help.pdf"),
+    ("calc [expresion]", "help.calc"),
+    ("cal [MM [AAAA]|add AAAA-MM-DD [HH:MM] texto|del AAAA-MM-DD n|events [fecha]|gui]", "help.cal"),
+    ("alarm [list|add HH:MM [una|diario|laborables] [texto]|del n|on n|off n|timer <dur> [texto]|cancel t<n>|gui]", "help.alarm"),
+    ("index [status|rebuild|find <texto>]", "help.index"),
+    ("fswatch", "help.fswatch"),
+    ("ln -s <destino> <enlace>", "help.ln"),
+    ("readlink <ruta>", "help.readlink"),
+    ("xattr <ruta> [get <nombre>|set <nombre> <valor>|rm <nombre>]", "help.xattr"),
+    ("chmod <modo> <ruta>", "help.chmod"),
+    ("chown <usuario>[:grupo] <ruta>", "help.chown"),
+    ("stat <ruta>", "help.stat"),
+    ("su [usuario]", "help.su"),
+    ("id | whoami", "help.id"),
+    ("chkdsk [reparar]", "help.chkdsk"),
+    ("parts", "help.parts"),
+    ("uninstall [n [--yes]]", "help.uninstall"),
+    ("heapcheck", "help.heapcheck"),
+    ("replay [record|play|stop]", "help.replay"),
+    ("osk [on|off|auto|show|hide|layout es|en]", "help.osk"),
+];
+
+/// `(usage, description key)` for the desktop terminal. An empty key prints
+/// the usage alone.
+pub(super) const DESKTOP_HELP: &[(&str, &str)] = &[
+    ("ls", "help.ls"),
+    ("cd <dir>", "help.cd"),
+    ("cat <file>", "help.cat"),
+    ("cp <src> <dst>", "help.cp"),
+    ("mv <src> <dst>", "help.mv"),
+    ("disks", "help.disks"),
+    ("vols", "help.vols"),
+    ("mount <n>", "help.mount"),
+    ("mount <dispositivo> <dir>", "help.mount_at"),
+    ("umount <dir>", "help.umount"),
+    ("mounts", "help.mounts"),
+    ("df", "help.df"),
+    ("unmount", "help.unmount"),
+    ("cpdev <src_dev> <src_path> <dst_dev> <dst_path>", "help.cpdev"),
+    ("net", "help.net"),
+    ("net dhcp", "help.net_dhcp"),
+    ("net static", "help.net_static"),
+    ("net static <ip> <prefijo> <gateway>", "help.net_static_custom"),
+    ("net mode", "help.net_mode"),
+    ("net https <on|off|status>", "help.net_https"),
+    ("net diag", "help.net_diag"),
+    ("net bench [url]", "help.net_bench"),
+    ("net pool [clear]", "help.net_pool"),
+    ("net jobs [get <url>|cancel <id>]", "help.net_jobs"),
+    ("net profile [list|save <nombre>|load <nombre>|delete <nombre>]", "help.net_profile"),
+    ("net portal [check|open]", "help.net_portal"),
+    ("net stats", "help.net_stats"),
+    ("net arp [list|flush|add <ip> <mac>|del <ip>]", "help.net_arp"),
+    ("net mtu [[intel|virtio] <bytes>|default]", "help.net_mtu"),
+    ("wifi", "help.wifi"),
+    ("wifi scan", "help.wifi_scan"),
+    ("wifi connect <ssid> <clave>", "help.wifi_connect"),
+    ("wifi disconnect", "help.wifi_disconnect"),
+    ("wifi failover <ethernet|wifi|status>", "help.wifi_failover"),
+    ("fetch <url> [file_8_3]", "help.fetch"),
+    ("web backend <builtin|litehtml|litehtmlrt|servort|vaev|webkit|servohost|cef|status>", "help.web_backend"),
+    ("web litehtmlrt <status|target <path>>", "help.web_litehtmlrt"),
+    ("web servort <status|target <path>|mode <safe|real|status>|open <url>|frame|input ...>", "help.web_servort"),
+    ("web vaev status", "help.web_vaev"),
+    ("web vaev input <click x y|scroll d|key K|text T|back|forward|reload>", ""),
+    ("web native <on|off|status>", "help.web_native"),
+    ("web webkit <status|endpoint|ping|open|frame|input>", "help.web_webkit"),
+    ("web servohost <status|endpoint|ping|open|frame|input>", "help.web_servohost"),
+    ("wry ...", "help.alias_webkit"),
+    ("servohost ...", "help.alias_servohost"),
+    ("servort ...", "help.alias_servort"),
+    ("mem", "help.mem"),
+    ("acpi", "help.acpi"),
+    ("suspend [auto|s3|s2idle]", "help.suspend"),
+    ("log [tail <n>] | dmesg", "help.log"),
+    ("log save", "help.log_save"),
+    ("log file", "help.log_file"),
+    ("log remote <ip[:puerto]> [udp|tcp] | log remote <off|status|level <nivel>>", "help.log_remote"),
+    ("hwinfo [cpu|mem|pci|disk|display|net|input|gui] | about", "help.hwinfo"),
+    ("cryptobench [KiB]", "help.cryptobench"),
+    ("bench [mem|fs|net|gui|all] [--json]", "help.bench"),
+    ("random [status|<bytes>]", "help.random"),
+    ("secureboot [status|enroll|cancel]", "help.secureboot"),
+    ("tpm [status|pcrs|log]", "help.tpm"),
+    ("crypt [status|unlock [contrasena]]", "help.crypt"),
+    ("bootvar [list|order|enable|disable|delete|next|clean|restore|gui] [-n]", "help.bootvar"),
+    ("osprober [list|cfg|update]", "help.osprober"),
+    ("linuxboot [list|boot <n> [linea]|cmdline [texto]]", "help.linuxboot"),
+    ("lspci [-v] | lspci rescan | lspci drivers", "help.lspci"),
+    ("devices [tree|class [nombre]|show <dispositivo>]", "help.devices"),
+    ("mod [list|load <archivo.rko>|unload <nombre>|dev]", "help.mod"),
+    ("host [status|ls|cat|get|put|mkdir|rm]", "help.host"),
+    ("selftest [list|<suite>[::test]]", "help.selftest"),
+    ("firewall [list|allow|deny <host>[:puerto]|rm <n>|policy allow|deny|check]", "help.firewall"),
+    ("gemini [pins|forget <host>]", "help.gemini"),
+    ("ftp [status|open <ftp[s]://host>|ls|cd|pwd|get|put|close]", "help.ftp"),
+    ("mail [status|setup <email> <clave>|set|show|inbox [n]|read <n>|send <para> <archivo>|gui]", "help.mail"),
+    ("gzip [-1..-9] <archivo> [salida] | gunzip <archivo.gz> [salida]", "help.gzip"),
+    ("tar xf|tf <archivo.tar[.gz]> [destino] | tar cf|czf <archivo> <ruta>...", "help.tar"),
+    ("unzip [-l] <archivo.zip> [destino] | zip <archivo.zip> <ruta>...", "help.zip"),
+    ("pkg [repo <url>|update|search|info|list|install <pkg>...|remove <pkg>|autoremove|upgrade]", "help.pkg"),
+    ("notify [list|clear|send|warn|error <titulo> [| texto]]", "help.notify"),
+    ("config [list [prefijo]|get <clave>|set <clave> <valor>|unset <clave>|save]", "help.config"),
+    ("lang [es|en|<codigo>]", "help.lang"),
+    ("scale [1x|1.5x|2x]", "help.scale"),
+    ("effects [on|off|auto] | effects opacity <20-100> [titulo]", "help.effects"),
+    ("workspace [n] | workspace move <n> [titulo]", "help.workspace"),
+    ("session [status|save|load|clear|restore ask|always|never]", "help.session"),
+    ("mic [status|rec|stop|gui]", "help.mic"),
+    (
+        "sound [status|list|play <nombre>|beep [hz] [ms]|note <n> [ms] [programa]|midi <archivo>|on|off|output auto|speaker]",
+        "help.sound",
+    ),
+    ("remote [status|vnc on|off|auto|http on|off|auto]", "help.remote"),
+    ("usb [list|rescan]", "help.usb"),
+    (
+        "thunderbolt [status|authorize <n>|deny <n>|security none|user|secure|dponly|forget]",
+        "help.thunderbolt",
+    ),
+    ("power [status|mode auto|s3|s2idle|lid suspend|ignore|acpi]", "help.power"),
+    ("cmdline", "help.cmdline"),
+    ("gamepad [list|rescan|test|map [<boton> <tecla>]|deadzone <n>]", "help.gamepad"),
+    ("perf [status|hud [on|off]|cap <fps>|off|auto]", "help.perf"),
+    ("trace [status|on|off|clear|save [archivo]]", "help.trace"),
+    ("checkpoint [save|restore|info] [nombre]", "help.checkpoint"),
+    ("boottime", "help.boottime"),
+    ("clocksource", "help.clocksource"),
+    ("async", "help.async"),
+    ("jobs", "help.jobs"),
+    ("touch [list|rescan|orientation <normal|left|right|inverted>]", "help.touch"),
+    ("gamma [<0.5-3.0>|temp <K>|night off|on|schedule [HH:MM HH:MM]|backend auto|hw|sw]", "help.gamma"),
+    ("print [list|discover|add <uri> [nombre]|default <n>|info [n]|jobs|cancel <id>|test [n]]", "help.print"),
+    ("pdf [info <archivo>|text <archivo> [pagina]|open <archivo|url>]", "help.pdf"),
+    ("calc [expresion]", "help.calc"),
+    ("cal [MM [AAAA]|add AAAA-MM-DD [HH:MM] texto|del AAAA-MM-DD n|events [fecha]|gui]", "help.cal"),
+    ("alarm [list|add HH:MM [una|diario|laborables] [texto]|del n|on n|off n|timer <dur> [texto]|cancel t<n>|gui]", "help.alarm"),
+    ("index [status|rebuild|find <texto>]", "help.index"),
+    ("fswatch", "help.fswatch"),
+    ("ln -s <destino> <enlace>", "help.ln"),
+    ("readlink <ruta>", "help.readlink"),
+    ("xattr <ruta> [get <nombre>|set <nombre> <valor>|rm <nombre>]", "help.xattr"),
+    ("chmod <modo> <ruta>", "help.chmod"),
+    ("chown <usuario>[:grupo] <ruta>", "help.chown"),
+    ("stat <ruta>", "help.stat"),
+    ("su [usuario]", "help.su"),
+    ("id | whoami", "help.id"),
+    ("chkdsk [reparar]", "help.chkdsk"),
+    ("parts", "help.parts"),
+    ("uninstall [n [--yes]]", "help.uninstall"),
+    ("heapcheck", "help.heapcheck"),
+    ("replay [record|play|stop]", "help.replay"),
+    ("osk [on|off|auto|show|hide|layout es|en]", "help.osk"),
+    ("screenshot", "help.screenshot"),
+    ("stream <status|flush|auto on|auto off|auto status>", "help.stream"),
+    ("tasks <status|clear|cancel <id>|tune ...>", "help.tasks"),
+    (
+        "install [--autoport] <package.rpx|package.zip|package.tar|package.tar.gz|package.deb|setup.exe> [app_id]",
+        "help.install",
+    ),
+    ("entry <archivo> [app_id]", "help.entry"),
+    (
+        "linux inspect <elf> | linux run <elf> [args...] | linux runreal <elf> [args...] | linux runrealx <elf> [args...] | linux launch <elf> [args...] | linux launchmeta [--strict] <elf> | linux transfer <on|off|status> | linux runtime <quick|deep|status> | linux guest <status|start|rootfs|share|prefix|map> | linux app <run|map|status> | linux proc <start|startm|startx|startmx|status|step|stop> | linux runloop <start|startx|startm|startmx|status|step|stop> | linux bridge <open|close|status|test>",
+        "",
+    ),
+    ("host newlib porting", "help.newlib"),
+    ("ruby -e <code> | ruby <file.rb>", "help.ruby"),
+    ("runapp <layout.rml>", "help.runapp"),
+    ("ide", "help.ide"),
+    ("clear", "help.clear"),
+    ("help", "help.help"),
+    ("cppdoom", "help.cppdoom"),
+    ("shell", "help.shell"),
+];
+
+/// Help for the text shell, usages padded into a column like the original
+/// hand-written list.
+pub fn shell_help_lines() -> Vec<String> {
+    let mut out = Vec::with_capacity(SHELL_HELP.len() + 1);
+    out.push(super::tr("help.shell_header"));
+    for (usage, key) in SHELL_HELP.iter() {
+        let desc = super::tr(key);
+        if usage.len() < 15 {
+            out.push(alloc::format!("  {:<15}- {}", usage, desc));
+        } else {
+            out.push(alloc::format!("  {} - {}", usage, desc));
+        }
+    }
+    out
+}
+
+pub fn desktop_help_lines() -> Vec<String> {
+    let mut out = Vec::with_capacity(DESKTOP_HELP.len() + 1);
+    out.push(super::tr("help.desktop_header"));
+    for (usage, key) in DESKTOP_HELP.iter() {
+        if key.is_empty() {
+            out.push(alloc::format!("  {}", usage));
+        } else {
+            out.push(alloc::format!("  {} - {}", usage, super::tr(key)));
+        }
+    }
+    out
+}
@@ -0,0 +1,270 @@
+//! Interface language: message catalogs and the Spanish/English switch.
+//!
+//! Texts are looked up by key with `tr`/`trf`. The built-in catalog has
+//! Spanish and English; a language pack `\REDUXOS\LANG\<CODE>.TXT` ("key =
+//! text" lines, `#` comments) overrides entries or adds another language,
+//! which falls back to English for missing keys. The selected code is the
+//! `system.locale` config key; `main` reads it from the boot volume before
+//! the installer and boot selector draw anything. Numbers, sizes and dates
+//! follow the same code through [`format`].
+
+mod catalog;
+pub mod format;
+mod help;
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::spinlock::SpinLock;
+
+pub use help::{desktop_help_lines, shell_help_lines};
+
+pub const LOCALE_KEY: &str = "system.locale";
+pub const LANG_DIR: &str = "LANG";
+const PACK_MAX_BYTES: usize = 64 * 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Lang {
+    Es,
+    En,
+}
+
+impl Lang {
+    pub const DEFAULT: Lang = Lang::Es;
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "es" => Some(Lang::Es),
+            "en" => Some(Lang::En),
+            _ => None,
+        }
+    }
+
+    pub const fn code(self) -> &'static str {
+        match self {
+            Lang::Es => "es",
+            Lang::En => "en",
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Lang::Es => "Espanol",
+            Lang::En => "English",
+        }
+    }
+}
+
+/// Two to eight lower-case letters, optionally with a region ("pt-br").
+pub fn valid_code(code: &str) -> bool {
+    (2..=8).contains(&code.len()) && code.bytes().all(|b| b.is_ascii_lowercase() || b == b'-') && !code.starts_with('-')
+}
+
+/// `key = text` lines of a language pack; later keys win.
+pub fn parse_pack(raw: &[u8]) -> BTreeMap<String, String> {
+    let mut out = BTreeMap::new();
+    let text = String::from_utf8_lossy(raw);
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            let key = key.trim();
+            if !key.is_empty() {
+                out.insert(String::from(key), String::from(value.trim()));
+            }
+        }
+    }
+    out
+}
+
+struct Locale {
+    /// Code as configured; may name a pack-only language.
+    code: String,
+    base: Lang,
+    pack: BTreeMap<String, String>,
+}
+
+impl Locale {
+    const fn new() -> Self {
+        Self {
+            code: String::new(),
+            base: Lang::DEFAULT,
+            pack: BTreeMap::new(),
+        }
+    }
+
+    fn set(&mut self, code: &str, pack: Option<&[u8]>) {
+        self.base = Lang::from_code(code).unwrap_or(Lang::En);
+        self.code = String::from(code);
+        self.pack = match pack {
+            Some(raw) if raw.len() <= PACK_MAX_BYTES => parse_pack(raw),
+            _ => BTreeMap::new(),
+        };
+    }
+
+    fn lookup(&self, key: &str) -> String {
+        if let Some(text) = self.pack.get(key) {
+            return text.clone();
+        }
+        match catalog::MESSAGES.iter().find(|(k, _, _)| *k == key) {
+            Some((_, es, en)) => String::from(if self.base == Lang::Es { *es } else { *en }),
+            None => String::from(key),
+        }
+    }
+
+    fn code(&self) -> &str {
+        if self.code.is_empty() {
+            self.base.code()
+        } else {
+            self.code.as_str()
+        }
+    }
+}
+
+static LOCALE: SpinLock<Locale> = SpinLock::new(Locale::new());
+
+/// Text for `key` in the current language.
+pub fn tr(key: &str) -> String {
+    LOCALE.lock().lookup(key)
+}
+
+/// `tr` with each `{}` replaced by the next argument.
+pub fn trf(key: &str, args: &[&dyn core::fmt::Display]) -> String {
+    fill(tr(key).as_str(), args)
+}
+
+fn fill(template: &str, args: &[&dyn core::fmt::Display]) -> String {
+    use core::fmt::Write;
+    let mut out = String::with_capacity(template.len() + 16);
+    let mut args = args.iter();
+    let mut rest = template;
+    while let Some(pos) = rest.find("{}") {
+        out.push_str(&rest[..pos]);
+        match args.next() {
+            Some(arg) => {
+                let _ = write!(out, "{}", arg);
+            }
+            None => out.push_str("{}"),
+        }
+        rest = &rest[pos + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn current_code() -> String {
+    String::from(LOCALE.lock().code())
+}
+
+/// Switch language. `pack` is the raw `<CODE>.TXT`, if the volume has one.
+pub fn set_language(code: &str, pack: Option<&[u8]>) {
+    LOCALE.lock().set(code, pack);
+}
+
+/// Pack for `code` from `\REDUXOS\LANG` on the mounted volume.
+fn read_pack(code: &str) -> Option<Vec<u8>> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    if fat.init_status != crate::fat32::InitStatus::Success {
+        return None;
+    }
+    let path = alloc::format!("REDUXOS/{}", LANG_DIR);
+    let (_, dir) = fat.resolve_path(fat.root_cluster, path.as_str()).ok()?;
+    let name = alloc::format!("{}.TXT", code.to_ascii_uppercase());
+    fat.read_file_in_dir(dir, name.as_str()).ok()
+}
+
+fn on_locale_changed(_key: &str, value: Option<&crate::config::ConfigValue>) {
+    let code = match value {
+        Some(crate::config::ConfigValue::Str(code)) if valid_code(code.as_str()) => code.clone(),
+        _ => String::from(Lang::DEFAULT.code()),
+    };
+    let pack = read_pack(code.as_str());
+    set_language(code.as_str(), pack.as_deref());
+}
+
+/// Follow `system.locale` changes from now on.
+pub fn init() {
+    crate::config::watch(LOCALE_KEY, on_locale_changed);
+}
+
+/// Shared implementation of `lang`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let arg = args.trim().to_ascii_lowercase();
+    let mut out = Vec::new();
+    if arg.is_empty() || arg == "status" {
+        let (code, pack_len) = {
+            let locale = LOCALE.lock();
+            (String::from(locale.code()), locale.pack.len())
+        };
+        let name = Lang::from_code(code.as_str()).map(Lang::name).unwrap_or("pack");
+        out.push(trf("lang.current", &[&code, &name]));
+        if pack_len > 0 {
+            out.push(trf(
+                "lang.pack",
+                &[&alloc::format!("{}.TXT", code.to_ascii_uppercase()), &pack_len],
+            ));
+        } else {
+            out.push(tr("lang.builtin"));
+        }
+        let style = format::Style::current();
+        out.push(trf(
+            "lang.format",
+            &[&style.integer(1_234_567), &style.size(1536 * 1024), &format::now()],
+        ));
+        out.push(tr("lang.available"));
+        return out;
+    }
+    if !valid_code(arg.as_str()) {
+        out.push(trf("lang.invalid", &[&arg]));
+        return out;
+    }
+    let result = crate::config::set(LOCALE_KEY, crate::config::ConfigValue::Str(arg.clone()));
+    // The watcher already switched; make sure even if the value was unchanged.
+    if current_code() != arg {
+        on_locale_changed(LOCALE_KEY, Some(&crate::config::ConfigValue::Str(arg.clone())));
+    }
+    out.push(trf("lang.set", &[&arg]));
+    if let Err(e) = result {
+        out.push(alloc::format!("config: {}", e));
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "i18n";
+
+    fn lookup_falls_back_to_english_then_key() {
+        let mut locale = Locale::new();
+        crate::selftest::ensure_eq(locale.lookup("menu.shutdown").as_str(), "Apagar", "es por defecto")?;
+        locale.set("en", None);
+        crate::selftest::ensure_eq(locale.lookup("menu.shutdown").as_str(), "Shut down", "en")?;
+        locale.set("fr", Some(b"# pack\nmenu.shutdown = Eteindre\n"));
+        crate::selftest::ensure_eq(locale.lookup("menu.shutdown").as_str(), "Eteindre", "pack")?;
+        crate::selftest::ensure_eq(locale.lookup("menu.restart").as_str(), "Restart", "pack cae a en")?;
+        crate::selftest::ensure_eq(locale.lookup("no.such.key").as_str(), "no.such.key", "clave")
+    }
+
+    fn fill_replaces_in_order() {
+        crate::selftest::ensure_eq(fill("{} de {}", &[&1, &"dos"]).as_str(), "1 de dos", "args")?;
+        crate::selftest::ensure_eq(fill("{} y {}", &[&1]).as_str(), "1 y {}", "faltan args")
+    }
+
+    fn catalog_keys_are_unique_and_help_is_covered() {
+        for (i, (key, _, _)) in catalog::MESSAGES.iter().enumerate() {
+            crate::selftest::ensure(
+                !catalog::MESSAGES[..i].iter().any(|(k, _, _)| k == key),
+                alloc::format!("duplicada {}", key).as_str(),
+            )?;
+        }
+        for (usage, key) in help::SHELL_HELP.iter().chain(help::DESKTOP_HELP.iter()) {
+            crate::selftest::ensure(
+                key.is_empty() || catalog::MESSAGES.iter().any(|(k, _, _)| k == key),
+                alloc::format!("{} ({})", key, usage).as_str(),
+            )?;
+        }
+        Ok(())
+    }
+}
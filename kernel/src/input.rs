@@ -1,18 +1,31 @@
+use alloc::collections::VecDeque;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use crate::hal::{cli, hlt, inb, outb, pause};
+use crate::spinlock::SpinLock;
 use uefi::proto::console::text::{Key, ScanCode};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum RuntimeKey {
     Esc,
     F1,
     F2,
+    F12,
     Up,
     Down,
     Left,
     Right,
+    PageUp,
+    PageDown,
+    /// Space typed while a Super (Windows) key is held.
+    SuperSpace,
+    /// Super+1..9, with `shift` when Shift is held too.
+    SuperDigit {
+        digit: u8,
+        shift: bool,
+    },
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum RuntimeInput {
     Key(RuntimeKey),
     Char(char),
@@ -22,6 +35,9 @@ pub enum RuntimeInput {
 }
 
 static mut SHIFT_DOWN: bool = false;
+static SUPER_DOWN: AtomicBool = AtomicBool::new(false);
+/// An 0xE0 prefix byte arrived and the next scancode is an extended key.
+static EXTENDED_PENDING: AtomicBool = AtomicBool::new(false);
 
 fn decode_ascii(scancode: u8, shift: bool) -> Option<char> {
     const MAP: [char; 58] = [
@@ -50,7 +66,185 @@ fn decode_ascii(scancode: u8, shift: bool) -> Option<char> {
     }
 }
 
+// ---------------------------------------------------------------------------
+// virtio-input (QEMU virtio-keyboard / virtio-mouse / virtio-tablet)
+// Reports are queued by crate::virtio::input and consumed before the
+// PS/2, UEFI keyboard and UEFI pointer sources.
+// ---------------------------------------------------------------------------
+
+/// One EV_SYN-delimited pointer report from a virtio-input device.
+#[derive(Clone, Copy)]
+pub struct VirtioPointerReport {
+    pub dx: i32,
+    pub dy: i32,
+    pub wheel: i32,
+    pub left: bool,
+    pub right: bool,
+    /// Tablet position already scaled to screen pixels.
+    pub absolute: Option<(i32, i32)>,
+}
+
+const VIRTIO_QUEUE_LIMIT: usize = 256;
+
+/// What `virtio::input::poll` reported and the input side has not taken yet.
+struct VirtioInput {
+    pointer_queue: VecDeque<VirtioPointerReport>,
+    /// Key plus whether the device sent it as an autorepeat.
+    key_queue: VecDeque<(RuntimeInput, bool)>,
+    shift_down: bool,
+    /// Code of the last key pressed on virtio-input until its release arrives.
+    held_code: Option<u16>,
+    abs_last: Option<(i32, i32)>,
+    abs_warp: Option<(i32, i32)>,
+}
+
+static VIRTIO: SpinLock<VirtioInput> = SpinLock::new(VirtioInput {
+    pointer_queue: VecDeque::new(),
+    key_queue: VecDeque::new(),
+    shift_down: false,
+    held_code: None,
+    abs_last: None,
+    abs_warp: None,
+});
+static VIRTIO_SUPER_DOWN: AtomicBool = AtomicBool::new(false);
+/// Origin of the last key `poll_input_uefi` returned: `None` for firmware
+/// input (no release events, the firmware repeats), `Some(autorepeat)` for
+/// virtio-input. Stored as 0, 1 (`Some(false)`) or 2 (`Some(true)`).
+static LAST_KEY_REPEAT: AtomicU8 = AtomicU8::new(0);
+
+fn set_last_key_repeat(repeat: Option<bool>) {
+    let value = match repeat {
+        None => 0,
+        Some(false) => 1,
+        Some(true) => 2,
+    };
+    LAST_KEY_REPEAT.store(value, Ordering::Relaxed);
+}
+
+pub fn screen_dimensions() -> (u32, u32) {
+    unsafe { (SCREEN_W, SCREEN_H) }
+}
+
+pub fn push_virtio_pointer(report: VirtioPointerReport) {
+    let mut virtio = VIRTIO.lock();
+    if virtio.pointer_queue.len() >= VIRTIO_QUEUE_LIMIT {
+        virtio.pointer_queue.pop_front();
+    }
+    virtio.pointer_queue.push_back(report);
+}
+
+/// Last tablet position handed out, or the screen centre before the first one.
+pub fn virtio_absolute_position() -> (i32, i32) {
+    let (width, height) = screen_dimensions();
+    let last = VIRTIO.lock().abs_last;
+    last.unwrap_or(((width / 2) as i32, (height / 2) as i32))
+}
+
+/// Linux evdev key code + value (0 release, 1 press, 2 autorepeat).
+pub fn push_virtio_key(code: u16, value: u32) {
+    const KEY_LEFTSHIFT: u16 = 42;
+    const KEY_RIGHTSHIFT: u16 = 54;
+    const KEY_SPACE: u16 = 57;
+    const KEY_LEFTMETA: u16 = 125;
+    const KEY_RIGHTMETA: u16 = 126;
+    let mut virtio = VIRTIO.lock();
+    if code == KEY_LEFTSHIFT || code == KEY_RIGHTSHIFT {
+        virtio.shift_down = value != 0;
+        return;
+    }
+    if code == KEY_LEFTMETA || code == KEY_RIGHTMETA {
+        VIRTIO_SUPER_DOWN.store(value != 0, Ordering::Relaxed);
+        return;
+    }
+    if value == 0 {
+        if virtio.held_code == Some(code) {
+            virtio.held_code = None;
+        }
+        return;
+    }
+    let input = match code {
+        1 => Some(RuntimeInput::Key(RuntimeKey::Esc)),
+        59 => Some(RuntimeInput::Key(RuntimeKey::F1)),
+        60 => Some(RuntimeInput::Key(RuntimeKey::F2)),
+        88 => Some(RuntimeInput::Key(RuntimeKey::F12)),
+        103 => Some(RuntimeInput::Key(RuntimeKey::Up)),
+        105 => Some(RuntimeInput::Key(RuntimeKey::Left)),
+        106 => Some(RuntimeInput::Key(RuntimeKey::Right)),
+        108 => Some(RuntimeInput::Key(RuntimeKey::Down)),
+        104 => Some(RuntimeInput::Key(RuntimeKey::PageUp)),
+        109 => Some(RuntimeInput::Key(RuntimeKey::PageDown)),
+        KEY_SPACE if VIRTIO_SUPER_DOWN.load(Ordering::Relaxed) => Some(RuntimeInput::Key(RuntimeKey::SuperSpace)),
+        2..=10 if VIRTIO_SUPER_DOWN.load(Ordering::Relaxed) => Some(RuntimeInput::Key(RuntimeKey::SuperDigit {
+            digit: (code - 1) as u8,
+            shift: virtio.shift_down,
+        })),
+        14 => Some(RuntimeInput::Backspace),
+        28 | 96 => Some(RuntimeInput::Enter),
+        // Key codes 1..=57 line up with PS/2 set 1 make codes.
+        code if code < 0x80 => {
+            decode_ascii(code as u8, virtio.shift_down).map(RuntimeInput::Char)
+        }
+        _ => None,
+    };
+    if let Some(input) = input {
+        virtio.held_code = Some(code);
+        if virtio.key_queue.len() >= VIRTIO_QUEUE_LIMIT {
+            virtio.key_queue.pop_front();
+        }
+        virtio.key_queue.push_back((input, value == 2));
+    }
+}
+
+fn poll_virtio_key() -> Option<RuntimeInput> {
+    crate::virtio::input::poll();
+    crate::kmod::poll_input();
+    let (input, repeat) = VIRTIO.lock().key_queue.pop_front()?;
+    set_last_key_repeat(Some(repeat));
+    Some(input)
+}
+
+/// Whether the last virtio-input key is still down.
+pub fn virtio_key_held() -> bool {
+    VIRTIO.lock().held_code.is_some()
+}
+
+/// See `LAST_KEY_REPEAT`.
+pub fn last_key_repeat() -> Option<bool> {
+    match LAST_KEY_REPEAT.load(Ordering::Relaxed) {
+        1 => Some(false),
+        2 => Some(true),
+        _ => None,
+    }
+}
+
+fn poll_virtio_pointer() -> Option<(i32, i32, i32, bool, bool)> {
+    crate::virtio::input::poll();
+    crate::kmod::poll_input();
+    let mut virtio = VIRTIO.lock();
+    let report = virtio.pointer_queue.pop_front()?;
+    let (mut dx, mut dy) = (report.dx, report.dy);
+    if let Some((x, y)) = report.absolute {
+        if let Some((last_x, last_y)) = virtio.abs_last {
+            dx = dx.saturating_add(x - last_x);
+            dy = dy.saturating_add(y - last_y);
+        }
+        virtio.abs_last = Some((x, y));
+        virtio.abs_warp = Some((x, y));
+    }
+    Some((dx, dy, report.wheel, report.left, report.right))
+}
+
+/// Exact cursor position from the last tablet report returned by `poll_mouse_uefi`.
+/// Callers apply it after the deltas so the cursor tracks the host pointer 1:1.
+pub fn take_absolute_pointer() -> Option<(i32, i32)> {
+    VIRTIO.lock().abs_warp.take()
+}
+
 pub fn poll_input() -> Option<RuntimeInput> {
+    if let Some(input) = poll_virtio_key() {
+        return Some(input);
+    }
+
     let status = unsafe { inb(0x64) };
     if (status & 0x01) == 0 {
         return None;
@@ -58,6 +252,35 @@ pub fn poll_input() -> Option<RuntimeInput> {
 
     let scancode = unsafe { inb(0x60) };
 
+    // E0-prefixed keys (arrows, PageUp/PageDown, Super). The prefix also wraps the
+    // fake shifts some keyboards send around them, so they skip the shift
+    // handling below.
+    if scancode == 0xE0 {
+        EXTENDED_PENDING.store(true, Ordering::Relaxed);
+        return None;
+    }
+    if EXTENDED_PENDING.swap(false, Ordering::Relaxed) {
+        return match scancode {
+            0x48 => Some(RuntimeInput::Key(RuntimeKey::Up)),
+            0x50 => Some(RuntimeInput::Key(RuntimeKey::Down)),
+            0x4B => Some(RuntimeInput::Key(RuntimeKey::Left)),
+            0x4D => Some(RuntimeInput::Key(RuntimeKey::Right)),
+            0x49 => Some(RuntimeInput::Key(RuntimeKey::PageUp)),
+            0x51 => Some(RuntimeInput::Key(RuntimeKey::PageDown)),
+            0x1C => Some(RuntimeInput::Enter),
+            // Left/right Super (Windows) keys.
+            0x5B | 0x5C => {
+                SUPER_DOWN.store(true, Ordering::Relaxed);
+                None
+            }
+            0xDB | 0xDC => {
+                SUPER_DOWN.store(false, Ordering::Relaxed);
+                None
+            }
+            _ => None,
+        };
+    }
+
     // Shift press/release.
     match scancode {
         0x2A | 0x36 => {
@@ -80,6 +303,12 @@ pub fn poll_input() -> Option<RuntimeInput> {
         0x01 => Some(RuntimeInput::Key(RuntimeKey::Esc)),
         0x3B => Some(RuntimeInput::Key(RuntimeKey::F1)),
         0x3C => Some(RuntimeInput::Key(RuntimeKey::F2)),
+        0x58 => Some(RuntimeInput::Key(RuntimeKey::F12)),
+        0x39 if SUPER_DOWN.load(Ordering::Relaxed) => Some(RuntimeInput::Key(RuntimeKey::SuperSpace)),
+        0x02..=0x0A if SUPER_DOWN.load(Ordering::Relaxed) => Some(RuntimeInput::Key(RuntimeKey::SuperDigit {
+            digit: scancode - 1,
+            shift: unsafe { SHIFT_DOWN },
+        })),
         0x0E => Some(RuntimeInput::Backspace),
         0x1C => Some(RuntimeInput::Enter),
         _ => {
@@ -91,6 +320,34 @@ pub fn poll_input() -> Option<RuntimeInput> {
 
 // UEFI keyboard input (USB works here). Only valid while Boot Services are active.
 pub fn poll_input_uefi() -> Option<RuntimeInput> {
+    let input = if crate::replay::playing() {
+        // Live keys are dropped while a recording plays.
+        while read_input_uefi().is_some() {}
+        crate::replay::next_key().map(|(input, repeat)| {
+            set_last_key_repeat(repeat);
+            input
+        })
+    } else {
+        let input = read_input_uefi();
+        if let Some(event) = input {
+            crate::replay::record_key(event, last_key_repeat());
+        }
+        input
+    };
+    if let Some(event) = input.as_ref() {
+        crate::random::add_input_timing(match event {
+            RuntimeInput::Char(ch) => *ch as u64,
+            _ => 0x100,
+        });
+    }
+    input
+}
+
+fn read_input_uefi() -> Option<RuntimeInput> {
+    if let Some(input) = poll_virtio_key() {
+        return Some(input);
+    }
+    set_last_key_repeat(None);
     uefi::system::with_stdin(|input| match input.read_key().ok().flatten() {
         Some(Key::Printable(c16)) => {
             let ch: char = c16.into();
@@ -104,10 +361,13 @@ pub fn poll_input_uefi() -> Option<RuntimeInput> {
             ScanCode::ESCAPE => Some(RuntimeInput::Key(RuntimeKey::Esc)),
             ScanCode::FUNCTION_1 => Some(RuntimeInput::Key(RuntimeKey::F1)),
             ScanCode::FUNCTION_2 => Some(RuntimeInput::Key(RuntimeKey::F2)),
+            ScanCode::FUNCTION_12 => Some(RuntimeInput::Key(RuntimeKey::F12)),
             ScanCode::UP => Some(RuntimeInput::Key(RuntimeKey::Up)),
             ScanCode::DOWN => Some(RuntimeInput::Key(RuntimeKey::Down)),
             ScanCode::LEFT => Some(RuntimeInput::Key(RuntimeKey::Left)),
             ScanCode::RIGHT => Some(RuntimeInput::Key(RuntimeKey::Right)),
+            ScanCode::PAGE_UP => Some(RuntimeInput::Key(RuntimeKey::PageUp)),
+            ScanCode::PAGE_DOWN => Some(RuntimeInput::Key(RuntimeKey::PageDown)),
             _ => None,
         },
         _ => None,
@@ -322,8 +582,43 @@ fn reset_absolute_pointer_uefi() {
 }
 
 /// Returns (dx, dy, wheel_delta, left_button, right_button) from any available pointing device.
-/// Checks both SimplePointer (USB mouse) and AbsolutePointer (touchpad) protocols.
+/// Checks virtio-input, SimplePointer (USB mouse) and AbsolutePointer (touchpad) sources.
 pub fn poll_mouse_uefi() -> Option<(i32, i32, i32, bool, bool)> {
+    let report = if crate::replay::playing() {
+        while read_mouse_uefi().is_some() {}
+        crate::replay::next_pointer().map(|p| {
+            let mut virtio = VIRTIO.lock();
+            virtio.abs_warp = p.absolute;
+            if p.absolute.is_some() {
+                virtio.abs_last = p.absolute;
+            }
+            (p.dx, p.dy, p.wheel, p.left, p.right)
+        })
+    } else {
+        let report = read_mouse_uefi();
+        if let Some((dx, dy, wheel, left, right)) = report {
+            crate::replay::record_pointer(crate::replay::Pointer {
+                dx,
+                dy,
+                wheel,
+                left,
+                right,
+                absolute: VIRTIO.lock().abs_warp,
+            });
+        }
+        report
+    };
+    if let Some((dx, dy, wheel, _, _)) = report {
+        crate::random::add_input_timing((dx as u32 as u64) << 32 | (dy as u16 as u64) << 16 | wheel as u16 as u64);
+    }
+    report
+}
+
+fn read_mouse_uefi() -> Option<(i32, i32, i32, bool, bool)> {
+    // 0. virtio-input (VMs): exact, deterministic coordinates.
+    if let Some(result) = poll_virtio_pointer() {
+        return Some(result);
+    }
     // 1. Try SimplePointer first (USB mice — fast, low latency)
     if let Some(result) = poll_simple_pointer() {
         return Some(result);
@@ -1,6 +1,9 @@
-use crate::pci::{PciDevice, read_bar};
+use crate::pci::{PciDevice, PciDriver, PciMatch, read_bar};
 use crate::println;
+use crate::spinlock::SpinLock;
 use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use smoltcp::phy::{Device, DeviceCapabilities, Medium, RxToken, TxToken};
 use smoltcp::time::Instant;
 
@@ -41,6 +44,10 @@ const REG_RAL: u32 = 0x05400; // Receive Address Low
 const REG_RAH: u32 = 0x05404; // Receive Address High
 const REG_GPRC: u32 = 0x04074; // Good Packets Received Count
 const REG_GPTC: u32 = 0x04080; // Good Packets Transmitted Count
+const REG_CRCERRS: u32 = 0x04000; // CRC Error Count
+const REG_RXERRC: u32 = 0x0400C; // RX Error Count
+const REG_MPC: u32 = 0x04010; // Missed Packets Count (RX FIFO full)
+const REG_RLPML: u32 = 0x05004; // Receive Long Packet Maximum Length
 
 const RING_SIZE: usize = 64; // Number of descriptors
 
@@ -60,6 +67,7 @@ const TXDCTL_ENABLE: u32 = 1 << 25;
 
 const RCTL_EN: u32 = 1 << 1;
 const RCTL_MPE: u32 = 1 << 4;
+const RCTL_LPE: u32 = 1 << 5;
 const RCTL_BAM: u32 = 1 << 15;
 const RCTL_SECRC: u32 = 1 << 26;
 const RXCTRL_RXEN: u32 = 1 << 0;
@@ -69,7 +77,14 @@ const SRRCTL_BSIZEPKT_SHIFT: u32 = 10;
 const ADV_RX_STAT_DD: u32 = 1 << 0;
 const ADV_RX_STAT_EOP: u32 = 1 << 1;
 const RX_MIN_FRAME_LEN: usize = 14;
-const RX_MAX_FRAME_LEN: usize = 2048;
+/// RX buffer for standard frames; jumbo MTUs switch to multi-page buffers.
+const RX_STANDARD_BUFFER_LEN: usize = 2048;
+/// Ethernet header, VLAN tag and FCS on top of the MTU.
+const FRAME_OVERHEAD: usize = 22;
+
+pub const MTU_DEFAULT: usize = 1500;
+/// I225/I226 take frames up to 9216 bytes.
+pub const MTU_MAX: usize = 9000;
 
 const TCTL_EN: u32 = 1 << 1;
 const TCTL_PSP: u32 = 1 << 3;
@@ -80,8 +95,15 @@ const TX_CMD_EOP: u8 = 1 << 0;
 const TX_CMD_IFCS: u8 = 1 << 1;
 const TX_CMD_RS: u8 = 1 << 3;
 
-pub static mut RX_COUNT: u64 = 0;
-pub static mut TX_COUNT: u64 = 0;
+pub static RX_COUNT: AtomicU64 = AtomicU64::new(0);
+pub static TX_COUNT: AtomicU64 = AtomicU64::new(0);
+/// The statistics registers clear on read, so totals are kept here.
+static RX_ERROR_TOTAL: AtomicU64 = AtomicU64::new(0);
+static RX_MISSED_TOTAL: AtomicU64 = AtomicU64::new(0);
+static MTU: AtomicUsize = AtomicUsize::new(MTU_DEFAULT);
+static RX_BUFFER_LEN: AtomicUsize = AtomicUsize::new(RX_STANDARD_BUFFER_LEN);
+/// Pages behind each RX buffer; grows for jumbo frames, never shrinks.
+static RX_BUFFER_PAGES: AtomicUsize = AtomicUsize::new(1);
 
 #[repr(C, packed)]
 #[derive(Copy, Clone)]
@@ -111,6 +133,10 @@ pub struct IntelNetDevice {
     tx_cur: usize,
 }
 
+// The rings are DMA memory owned by the device; `GLOBAL_INTEL_NET` serializes
+// every access to them.
+unsafe impl Send for IntelNetDevice {}
+
 impl IntelNetDevice {
     pub unsafe fn read_reg(&self, offset: u32) -> u32 {
         let ptr = (self.mmio_base + offset as u64) as *const u32;
@@ -127,8 +153,10 @@ impl IntelNetDevice {
         let ctrl = self.read_reg(REG_CTRL);
         self.write_reg(REG_CTRL, ctrl | CTRL_RST);
         
-        // Wait for reset to complete
-        uefi::boot::stall(10000);
+        // Wait for reset to complete: CTRL.RST self-clears. Keep the 1 ms
+        // the datasheets ask for before touching other registers.
+        uefi::boot::stall(1000);
+        crate::boottime::wait_until(10_000, 100, || self.read_reg(REG_CTRL) & CTRL_RST == 0);
         
         // Force Link Up (Set Link Up / SLU bit)
         let ctrl = self.read_reg(REG_CTRL);
@@ -139,10 +167,146 @@ impl IntelNetDevice {
         self.write_reg(REG_CTRL_EXT, ctrl_ext | CTRL_EXT_DRV_LOAD);
     }
 
+    /// Program the MAC address and both rings from the buffers already
+    /// allocated, after a reset. Used at init and again after resume, when
+    /// the NIC may have lost power.
+    unsafe fn program(&mut self) {
+        // Program MAC and mark address as valid (RAH.AV).
+        let ral_prog = (self.mac_addr[0] as u32)
+            | ((self.mac_addr[1] as u32) << 8)
+            | ((self.mac_addr[2] as u32) << 16)
+            | ((self.mac_addr[3] as u32) << 24);
+        let rah_prog = (self.mac_addr[4] as u32) | ((self.mac_addr[5] as u32) << 8) | RAH_AV;
+        self.write_reg(REG_RAL, ral_prog);
+        self.write_reg(REG_RAH, rah_prog);
+
+        // Point every RX descriptor at its buffer before enabling the queue.
+        for (i, buf_phys) in self.rx_buffers.iter().enumerate() {
+            let desc = IntelDescriptor {
+                addr: *buf_phys,
+                length: 0,
+                cso: 0,
+                cmd: 0,
+                status: 0,
+                css: 0,
+                special: 0,
+            };
+            core::ptr::write_volatile(self.rx_ring.add(i), desc);
+        }
+
+        // Init RX ring/registers.
+        self.write_reg(REG_RCTL, 0); // Disable
+        self.write_reg(REG_RDBAL, self.rx_ring_phys as u32);
+        self.write_reg(REG_RDBAH, (self.rx_ring_phys >> 32) as u32);
+        self.write_reg(REG_RDLEN, (RING_SIZE * 16) as u32);
+        // Use one-buffer advanced RX descriptors with 2KiB packet buffer.
+        self.write_reg(
+            REG_SRRCTL,
+            SRRCTL_DESCTYPE_ADV_ONEBUF | ((2048u32 >> SRRCTL_BSIZEPKT_SHIFT) & 0x7F),
+        );
+        self.write_reg(REG_RDH, 0);
+        self.write_reg(REG_RDT, 0);
+
+        let rxdctl = self.read_reg(REG_RXDCTL);
+        self.write_reg(REG_RXDCTL, rxdctl | RXDCTL_ENABLE);
+        let mut rx_wait = 0;
+        while rx_wait < 100 && (self.read_reg(REG_RXDCTL) & RXDCTL_ENABLE) == 0 {
+            uefi::boot::stall(1000);
+            rx_wait += 1;
+        }
+
+        // Enable RX: EN | MPE | BAM | SECRC.
+        self.write_reg(REG_RCTL, RCTL_EN | RCTL_MPE | RCTL_BAM | RCTL_SECRC);
+        let rxctrl = self.read_reg(REG_RXCTRL);
+        self.write_reg(REG_RXCTRL, rxctrl | RXCTRL_RXEN);
+        // Advertise the full RX ring after queue/rx path is enabled.
+        self.write_reg(REG_RDT, (RING_SIZE - 1) as u32);
+        // Clear TX descriptors.
+        for i in 0..RING_SIZE {
+            core::ptr::write_volatile(self.tx_ring.add(i), core::mem::zeroed());
+        }
+
+        // Init TX ring/registers.
+        self.write_reg(REG_TCTL, 0); // Disable
+        self.write_reg(REG_TDBAL, self.tx_ring_phys as u32);
+        self.write_reg(REG_TDBAH, (self.tx_ring_phys >> 32) as u32);
+        self.write_reg(REG_TDLEN, (RING_SIZE * 16) as u32);
+        self.write_reg(REG_TDH, 0);
+        self.write_reg(REG_TDT, 0);
+
+        let txdctl = self.read_reg(REG_TXDCTL);
+        self.write_reg(REG_TXDCTL, txdctl | TXDCTL_ENABLE);
+        let mut tx_wait = 0;
+        while tx_wait < 100 && (self.read_reg(REG_TXDCTL) & TXDCTL_ENABLE) == 0 {
+            uefi::boot::stall(1000);
+            tx_wait += 1;
+        }
+
+        // Typical legacy defaults used by Intel sample drivers.
+        self.write_reg(REG_TIPG, 10 | (8 << 10) | (12 << 20));
+
+        // Disable all interrupts
+        self.write_reg(REG_IMC, 0xFFFF_FFFF);
+        let _ = self.read_reg(REG_ICR); // Clear any pending causes.
+
+        // Enable TX with collision defaults (CT/COLD).
+        self.write_reg(REG_TCTL, TCTL_EN | TCTL_PSP | TCTL_CT | TCTL_COLD);
+
+        self.rx_cur = 0;
+        self.tx_cur = 0;
+    }
+
     pub unsafe fn is_link_up(&self) -> bool {
         let status = self.read_reg(REG_STATUS);
         (status & STATUS_LU) != 0
     }
+
+    /// Stop RX, point every descriptor at a buffer of `buffer_len` bytes and
+    /// start again with long packets allowed up to `max_frame`.
+    unsafe fn reconfigure_rx(&mut self, buffer_len: usize, max_frame: usize) -> Result<(), &'static str> {
+        let pages = buffer_len.div_ceil(crate::memory::PAGE_SIZE as usize);
+        let rctl = self.read_reg(REG_RCTL);
+        self.write_reg(REG_RCTL, rctl & !RCTL_EN);
+        if pages > RX_BUFFER_PAGES.load(Ordering::Relaxed) {
+            let mut buffers = Vec::with_capacity(RING_SIZE);
+            for _ in 0..RING_SIZE {
+                match crate::memory::allocate_dma_pages32(pages) {
+                    Some(buf_phys) => buffers.push(buf_phys),
+                    None => {
+                        self.write_reg(REG_RCTL, rctl);
+                        return Err("sin memoria DMA para los buffers jumbo");
+                    }
+                }
+            }
+            self.rx_buffers = buffers;
+            RX_BUFFER_PAGES.store(pages, Ordering::Relaxed);
+        }
+        for (i, buf_phys) in self.rx_buffers.iter().enumerate() {
+            let desc = IntelDescriptor {
+                addr: *buf_phys,
+                length: 0,
+                cso: 0,
+                cmd: 0,
+                status: 0,
+                css: 0,
+                special: 0,
+            };
+            core::ptr::write_volatile(self.rx_ring.add(i), desc);
+        }
+        self.write_reg(
+            REG_SRRCTL,
+            SRRCTL_DESCTYPE_ADV_ONEBUF | ((buffer_len as u32 >> SRRCTL_BSIZEPKT_SHIFT) & 0x7F),
+        );
+        self.write_reg(REG_RLPML, max_frame as u32);
+        self.write_reg(REG_RDH, 0);
+        self.write_reg(REG_RDT, 0);
+        self.rx_cur = 0;
+        RX_BUFFER_LEN.store(buffer_len, Ordering::Relaxed);
+        let long_packets = if buffer_len > RX_STANDARD_BUFFER_LEN { RCTL_LPE } else { 0 };
+        self.write_reg(REG_RCTL, RCTL_EN | RCTL_MPE | RCTL_BAM | RCTL_SECRC | long_packets);
+        self.write_reg(REG_RDT, (RING_SIZE - 1) as u32);
+        Ok(())
+    }
 }
 
 fn parse_rx_length(desc: &IntelDescriptor) -> Option<usize> {
@@ -153,22 +317,69 @@ fn parse_rx_length(desc: &IntelDescriptor) -> Option<usize> {
     let adv_len = (desc.status as usize) | ((desc.css as usize) << 8);
     let adv_valid = (adv_status_error & ADV_RX_STAT_DD) != 0
         && (adv_status_error & ADV_RX_STAT_EOP) != 0
-        && (RX_MIN_FRAME_LEN..=RX_MAX_FRAME_LEN).contains(&adv_len);
+        && (RX_MIN_FRAME_LEN..=RX_BUFFER_LEN.load(Ordering::Relaxed)).contains(&adv_len);
     if adv_valid { Some(adv_len) } else { None }
 }
 
-pub static mut GLOBAL_INTEL_NET: Option<IntelNetDevice> = None;
-
-pub fn init(device: PciDevice) {
-    if device.vendor_id != VENDOR_INTEL { return; }
+pub static GLOBAL_INTEL_NET: SpinLock<Option<IntelNetDevice>> = SpinLock::new(None);
 
-    println("Intel Net: Initializing Hardware...");
+pub static PCI_DRIVER: PciDriver = PciDriver {
+    name: "intel_net",
+    ids: &[PciMatch::vendor_class(0x8086, 0x02, 0x00)],
+    probe: init,
+};
+static PM_OPS: crate::pm::PmOps = crate::pm::PmOps {
+    name: "intel_net",
+    suspend,
+    resume,
+};
 
+fn enable_mmio_and_bus_master(device: &PciDevice) {
     unsafe {
-        // Ensure MMIO + bus mastering are enabled for DMA/register access.
         let cmd = crate::pci::read_config(device.bus, device.slot, device.func, 0x04);
         crate::pci::write_config(device.bus, device.slot, device.func, 0x04, cmd | 0x0006);
     }
+}
+
+/// Stop both queues so nothing is in flight while the machine sleeps.
+fn suspend() -> Result<(), &'static str> {
+    if let Some(dev) = GLOBAL_INTEL_NET.lock().as_ref() {
+        unsafe {
+            dev.write_reg(REG_IMC, 0xFFFF_FFFF);
+            dev.write_reg(REG_RCTL, 0);
+            dev.write_reg(REG_TCTL, 0);
+        }
+    }
+    Ok(())
+}
+
+/// The NIC comes out of S3 with its registers at power-on defaults: reset
+/// it and program the rings again, keeping jumbo buffers if the MTU needs them.
+fn resume() {
+    let mut guard = GLOBAL_INTEL_NET.lock();
+    let Some(dev) = guard.as_mut() else {
+        return;
+    };
+    let device = dev.pci;
+    enable_mmio_and_bus_master(&device);
+    unsafe {
+        dev.reset();
+        enable_mmio_and_bus_master(&device);
+        dev.program();
+        let buffer_len = RX_BUFFER_LEN.load(Ordering::Relaxed);
+        if buffer_len != RX_STANDARD_BUFFER_LEN {
+            let _ = dev.reconfigure_rx(buffer_len, mtu() + FRAME_OVERHEAD);
+        }
+    }
+}
+
+pub fn init(device: PciDevice) {
+    if device.vendor_id != VENDOR_INTEL { return; }
+
+    println("Intel Net: Initializing Hardware...");
+
+    // Ensure MMIO + bus mastering are enabled for DMA/register access.
+    enable_mmio_and_bus_master(&device);
 
     let mmio = match unsafe { read_bar(device.bus, device.slot, device.func, 0) } {
         Some(m) => m,
@@ -208,8 +419,7 @@ pub fn init(device: PciDevice) {
         dev.reset();
 
         // Some platforms clear command bits across device reset; force MMIO + bus mastering again.
-        let cmd_after_reset = crate::pci::read_config(device.bus, device.slot, device.func, 0x04);
-        crate::pci::write_config(device.bus, device.slot, device.func, 0x04, cmd_after_reset | 0x0006);
+        enable_mmio_and_bus_master(&device);
 
         // Read MAC from hardware registers.
         let ral = dev.read_reg(REG_RAL);
@@ -228,111 +438,43 @@ pub fn init(device: PciDevice) {
             println("Intel Net: Warning - invalid HW MAC, using fallback MAC.");
         }
 
-        // Program MAC and mark address as valid (RAH.AV).
-        let ral_prog = (dev.mac_addr[0] as u32)
-            | ((dev.mac_addr[1] as u32) << 8)
-            | ((dev.mac_addr[2] as u32) << 16)
-            | ((dev.mac_addr[3] as u32) << 24);
-        let rah_prog = (dev.mac_addr[4] as u32) | ((dev.mac_addr[5] as u32) << 8) | RAH_AV;
-        dev.write_reg(REG_RAL, ral_prog);
-        dev.write_reg(REG_RAH, rah_prog);
-
-        // Populate RX buffers/descriptors before enabling RX queue.
-        for i in 0..RING_SIZE {
+        // Populate RX buffers before the rings are programmed.
+        for _ in 0..RING_SIZE {
             let buf_phys = crate::memory::allocate_dma_page32().expect("RX Buffer DMA failed");
             dev.rx_buffers.push(buf_phys);
-            let desc = IntelDescriptor {
-                addr: buf_phys,
-                length: 0,
-                cso: 0,
-                cmd: 0,
-                status: 0,
-                css: 0,
-                special: 0,
-            };
-            core::ptr::write_volatile(dev.rx_ring.add(i), desc);
-        }
-
-        // Init RX ring/registers.
-        dev.write_reg(REG_RCTL, 0); // Disable
-        dev.write_reg(REG_RDBAL, dev.rx_ring_phys as u32);
-        dev.write_reg(REG_RDBAH, (dev.rx_ring_phys >> 32) as u32);
-        dev.write_reg(REG_RDLEN, (RING_SIZE * 16) as u32);
-        // Use one-buffer advanced RX descriptors with 2KiB packet buffer.
-        dev.write_reg(
-            REG_SRRCTL,
-            SRRCTL_DESCTYPE_ADV_ONEBUF | ((2048u32 >> SRRCTL_BSIZEPKT_SHIFT) & 0x7F),
-        );
-        dev.write_reg(REG_RDH, 0);
-        dev.write_reg(REG_RDT, 0);
-
-        let rxdctl = dev.read_reg(REG_RXDCTL);
-        dev.write_reg(REG_RXDCTL, rxdctl | RXDCTL_ENABLE);
-        let mut rx_wait = 0;
-        while rx_wait < 100 && (dev.read_reg(REG_RXDCTL) & RXDCTL_ENABLE) == 0 {
-            uefi::boot::stall(1000);
-            rx_wait += 1;
-        }
-
-        // Enable RX: EN | MPE | BAM | SECRC.
-        dev.write_reg(REG_RCTL, RCTL_EN | RCTL_MPE | RCTL_BAM | RCTL_SECRC);
-        let rxctrl = dev.read_reg(REG_RXCTRL);
-        dev.write_reg(REG_RXCTRL, rxctrl | RXCTRL_RXEN);
-        // Advertise the full RX ring after queue/rx path is enabled.
-        dev.write_reg(REG_RDT, (RING_SIZE - 1) as u32);
-        // Clear TX descriptors.
-        for i in 0..RING_SIZE {
-            core::ptr::write_volatile(dev.tx_ring.add(i), core::mem::zeroed());
-        }
-
-        // Init TX ring/registers.
-        dev.write_reg(REG_TCTL, 0); // Disable
-        dev.write_reg(REG_TDBAL, dev.tx_ring_phys as u32);
-        dev.write_reg(REG_TDBAH, (dev.tx_ring_phys >> 32) as u32);
-        dev.write_reg(REG_TDLEN, (RING_SIZE * 16) as u32);
-        dev.write_reg(REG_TDH, 0);
-        dev.write_reg(REG_TDT, 0);
-
-        let txdctl = dev.read_reg(REG_TXDCTL);
-        dev.write_reg(REG_TXDCTL, txdctl | TXDCTL_ENABLE);
-        let mut tx_wait = 0;
-        while tx_wait < 100 && (dev.read_reg(REG_TXDCTL) & TXDCTL_ENABLE) == 0 {
-            uefi::boot::stall(1000);
-            tx_wait += 1;
         }
+        dev.program();
 
-        // Typical legacy defaults used by Intel sample drivers.
-        dev.write_reg(REG_TIPG, 10 | (8 << 10) | (12 << 20));
-
-        // Disable all interrupts
-        dev.write_reg(REG_IMC, 0xFFFF_FFFF);
-        let _ = dev.read_reg(REG_ICR); // Clear any pending causes.
-
-        // Enable TX with collision defaults (CT/COLD).
-        dev.write_reg(REG_TCTL, TCTL_EN | TCTL_PSP | TCTL_CT | TCTL_COLD);
-
-        GLOBAL_INTEL_NET = Some(dev);
+        let node = crate::device::add_class_device(
+            Some(crate::device::pci_function(&device)), "eth", "net", "intel-net");
+        crate::device::set_attr(node, "address", crate::net::stats::mac_text(&dev.mac_addr).as_str());
+        crate::device::set_live(node, "operstate", || {
+            alloc::string::String::from(if is_link_up() { "up" } else { "down" })
+        });
+        crate::device::set_live(node, "mtu", || alloc::format!("{}", mtu()));
 
-        // Wait a bit for link
-        let mut timeout = 0;
-        while timeout < 100 {
-            if let Some(ref d) = GLOBAL_INTEL_NET {
-                if d.is_link_up() {
-                    println("Intel Net: Link is UP.");
-                    break;
-                }
-            }
-            uefi::boot::stall(10000);
-            timeout += 1;
-        }
+        *GLOBAL_INTEL_NET.lock() = Some(dev);
+    }
+    crate::pm::register(&PM_OPS);
+
+    // Link negotiation takes up to a second; DHCP copes with a late link,
+    // so finish the wait on an executor task instead of stalling boot.
+    if is_link_up() {
+        println("Intel Net: Link is UP.");
+    } else {
+        crate::boottime::defer("intel_net_link", 3_000_000, is_link_up);
     }
 
     println("Intel Net: Ready.");
 }
 
+pub fn is_present() -> bool {
+    GLOBAL_INTEL_NET.lock().is_some()
+}
+
 pub fn is_link_up() -> bool {
     unsafe {
-        GLOBAL_INTEL_NET.as_ref().map(|d| d.is_link_up()).unwrap_or(false)
+        GLOBAL_INTEL_NET.lock().as_ref().map(|d| d.is_link_up()).unwrap_or(false)
     }
 }
 
@@ -371,7 +513,7 @@ pub struct IntelNetDiag {
 
 pub fn get_diagnostics() -> Option<IntelNetDiag> {
     unsafe {
-        GLOBAL_INTEL_NET.as_ref().map(|dev| {
+        GLOBAL_INTEL_NET.lock().as_ref().map(|dev| {
             let rx_desc = core::ptr::read_volatile(dev.rx_ring.add(dev.rx_cur));
             IntelNetDiag {
                 pci_cmd: crate::pci::read_config(dev.pci.bus, dev.pci.slot, dev.pci.func, 0x04),
@@ -415,33 +557,21 @@ impl Device for IntelPhy {
     type TxToken<'a> = IntelTxToken<'a> where Self: 'a;
 
     fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let mut guard = GLOBAL_INTEL_NET.lock();
         unsafe {
-            if let Some(ref mut dev) = GLOBAL_INTEL_NET {
+            if let Some(dev) = guard.as_mut() {
                 let desc = core::ptr::read_volatile(dev.rx_ring.add(dev.rx_cur));
                 if let Some(len) = parse_rx_length(&desc) {
-                    let buf_phys = dev.rx_buffers[dev.rx_cur];
-
-                    let new_desc = IntelDescriptor {
-                        addr: buf_phys,
-                        length: 0,
-                        cso: 0,
-                        cmd: 0,
-                        status: 0,
-                        css: 0,
-                        special: 0,
-                    };
-                    core::ptr::write_volatile(dev.rx_ring.add(dev.rx_cur), new_desc);
-                    
-                    dev.write_reg(REG_RDT, dev.rx_cur as u32);
+                    // The frame is handed to smoltcp in place; the descriptor
+                    // goes back to the NIC when the token is dropped.
+                    let index = dev.rx_cur;
+                    let buf_phys = dev.rx_buffers[index];
                     dev.rx_cur = (dev.rx_cur + 1) % RING_SIZE;
 
-                    let mut data = alloc::vec![0u8; len];
-                    core::ptr::copy_nonoverlapping(buf_phys as *const u8, data.as_mut_ptr(), len);
-
-                    RX_COUNT += 1;
+                    RX_COUNT.fetch_add(1, Ordering::Relaxed);
 
-                    let rx = IntelRxToken(data);
-                    let tx = IntelTxToken { dev };
+                    let rx = IntelRxToken { index, buf_phys, len };
+                    let tx = IntelTxToken { _phy: PhantomData };
                     return Some((rx, tx));
                 }
             }
@@ -450,41 +580,74 @@ impl Device for IntelPhy {
     }
 
     fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
-        unsafe {
-            if let Some(ref mut dev) = GLOBAL_INTEL_NET {
-                return Some(IntelTxToken { dev });
-            }
+        if is_present() {
+            return Some(IntelTxToken { _phy: PhantomData });
         }
         None
     }
 
     fn capabilities(&self) -> DeviceCapabilities {
         let mut caps = DeviceCapabilities::default();
-        caps.max_transmission_unit = 1500;
+        // smoltcp counts the Ethernet header as part of the MTU.
+        caps.max_transmission_unit = mtu() + 14;
         caps.medium = Medium::Ethernet;
         caps
     }
 }
 
-pub struct IntelRxToken(Vec<u8>);
+/// A received frame still sitting in its RX ring buffer (zero-copy).
+pub struct IntelRxToken {
+    index: usize,
+    buf_phys: u64,
+    len: usize,
+}
+
 impl RxToken for IntelRxToken {
     fn consume<R, F>(self, f: F) -> R where F: FnOnce(&mut [u8]) -> R {
-        let mut buffer = self.0;
-        f(&mut buffer)
+        // The buffer is identity-mapped DMA memory owned by this descriptor
+        // until `drop` returns it to the NIC.
+        let buffer = unsafe { core::slice::from_raw_parts_mut(self.buf_phys as *mut u8, self.len) };
+        f(buffer)
     }
 }
 
+impl Drop for IntelRxToken {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(dev) = GLOBAL_INTEL_NET.lock().as_ref() {
+                let desc = IntelDescriptor {
+                    addr: self.buf_phys,
+                    length: 0,
+                    cso: 0,
+                    cmd: 0,
+                    status: 0,
+                    css: 0,
+                    special: 0,
+                };
+                core::ptr::write_volatile(dev.rx_ring.add(self.index), desc);
+                dev.write_reg(REG_RDT, self.index as u32);
+            }
+        }
+    }
+}
+
+/// Takes the device lock only while the frame is queued, so it can live
+/// next to an `IntelRxToken` whose drop takes it too.
 pub struct IntelTxToken<'a> {
-    dev: &'a mut IntelNetDevice,
+    _phy: PhantomData<&'a mut IntelPhy>,
 }
 
 impl<'a> TxToken for IntelTxToken<'a> {
     fn consume<R, F>(self, len: usize, f: F) -> R where F: FnOnce(&mut [u8]) -> R {
         let mut buffer = alloc::vec![0u8; len];
         let result = f(&mut buffer);
+        let mut guard = GLOBAL_INTEL_NET.lock();
+        let Some(dev) = guard.as_mut() else {
+            return result;
+        };
         unsafe {
-            let dev = self.dev;
-            let td_phys = crate::memory::allocate_dma_page32().expect("Temp TX DMA failed");
+            let pages = len.div_ceil(crate::memory::PAGE_SIZE as usize);
+            let td_phys = crate::memory::allocate_dma_pages32(pages).expect("Temp TX DMA failed");
             core::ptr::copy_nonoverlapping(buffer.as_ptr(), td_phys as *mut u8, len);
             
             let cur = dev.tx_cur;
@@ -505,7 +668,7 @@ impl<'a> TxToken for IntelTxToken<'a> {
             
             dev.tx_cur = next_tdt;
             dev.write_reg(REG_TDT, next_tdt as u32);
-            TX_COUNT += 1;
+            TX_COUNT.fetch_add(1, Ordering::Relaxed);
 
             // Wait for RS (Report Status)
         }
@@ -514,19 +677,56 @@ impl<'a> TxToken for IntelTxToken<'a> {
 }
 
 pub fn get_model_name() -> Option<&'static str> {
+    GLOBAL_INTEL_NET.lock().as_ref().map(|dev| {
+        match dev.pci.device_id {
+            DEVICE_I226_V => "Intel I226-V (2.5GbE)",
+            DEVICE_I226_LM => "Intel I226-LM (2.5GbE)",
+            DEVICE_I225_V => "Intel I225-V (2.5GbE)",
+            DEVICE_I225_LM => "Intel I225-LM (2.5GbE)",
+            _ => "Intel Ethernet",
+        }
+    })
+}
+
+/// `(rx_errors, rx_missed)` since boot: CRC and receive errors, and frames
+/// dropped because the RX ring was full.
+pub fn error_counters() -> (u64, u64) {
+    if let Some(dev) = GLOBAL_INTEL_NET.lock().as_ref() {
+        unsafe {
+            let errors = dev.read_reg(REG_CRCERRS) as u64 + dev.read_reg(REG_RXERRC) as u64;
+            RX_ERROR_TOTAL.fetch_add(errors, Ordering::Relaxed);
+            RX_MISSED_TOTAL.fetch_add(dev.read_reg(REG_MPC) as u64, Ordering::Relaxed);
+        }
+    }
+    (RX_ERROR_TOTAL.load(Ordering::Relaxed), RX_MISSED_TOTAL.load(Ordering::Relaxed))
+}
+
+pub fn mtu() -> usize {
+    MTU.load(Ordering::Relaxed)
+}
+
+/// Switch the MTU, reallocating RX buffers when frames outgrow them.
+pub fn set_mtu(mtu: usize) -> Result<(), &'static str> {
+    if !(crate::net::mtu::MTU_MIN..=MTU_MAX).contains(&mtu) {
+        return Err("MTU fuera de rango");
+    }
+    let mut guard = GLOBAL_INTEL_NET.lock();
     unsafe {
-        GLOBAL_INTEL_NET.as_ref().map(|dev| {
-            match dev.pci.device_id {
-                DEVICE_I226_V => "Intel I226-V (2.5GbE)",
-                DEVICE_I226_LM => "Intel I226-LM (2.5GbE)",
-                DEVICE_I225_V => "Intel I225-V (2.5GbE)",
-                DEVICE_I225_LM => "Intel I225-LM (2.5GbE)",
-                _ => "Intel Ethernet",
-            }
-        })
+        let Some(dev) = guard.as_mut() else {
+            return Err("sin NIC Intel");
+        };
+        let max_frame = mtu + FRAME_OVERHEAD;
+        let buffer_len = if max_frame <= RX_STANDARD_BUFFER_LEN {
+            RX_STANDARD_BUFFER_LEN
+        } else {
+            max_frame.div_ceil(1024) * 1024
+        };
+        dev.reconfigure_rx(buffer_len, max_frame)?;
+        MTU.store(mtu, Ordering::Relaxed);
     }
+    Ok(())
 }
 
 pub fn get_mac_address() -> Option<[u8; 6]> {
-    unsafe { GLOBAL_INTEL_NET.as_ref().map(|d| d.mac_addr) }
+    GLOBAL_INTEL_NET.lock().as_ref().map(|d| d.mac_addr)
 }
@@ -1,5 +1,7 @@
-use crate::pci::{read_bar, read_config, PciDevice};
+use crate::pci::{read_bar, read_config, PciDevice, PciDriver, PciMatch};
 use crate::println;
+use crate::spinlock::SpinLock;
+use crate::sync::Once;
 
 const VENDOR_INTEL: u16 = 0x8086;
 
@@ -19,6 +21,7 @@ const WIFI_STATUS_NOT_DETECTED: &str = "No detectado";
 const WIFI_STATUS_UNKNOWN_ID: &str = "Detectado (ID no mapeado, fase1)";
 const WIFI_STATUS_PHASE1_FW: &str = "Detectado (fase1 nativa, firmware pendiente)";
 const WIFI_STATUS_PHASE1_READY: &str = "Detectado (fase1 nativa, datapath listo)";
+const WIFI_STATUS_FAILED: &str = "Driver desactivado tras un fallo";
 
 const MAX_SSID_LEN: usize = 32;
 const MAX_PSK_LEN: usize = 64;
@@ -94,16 +97,38 @@ pub struct IntelWifiDevice {
     pub subsystem_device_id: u16,
 }
 
-pub static mut GLOBAL_INTEL_WIFI: Option<IntelWifiDevice> = None;
-pub static mut WIFI_STATUS: &str = WIFI_STATUS_NOT_DETECTED;
-static mut WIFI_PROFILE: Option<WifiProfile> = None;
-static mut WIFI_CONNECTED: bool = false;
-static mut WIFI_CONNECTED_SSID: [u8; MAX_SSID_LEN] = [0; MAX_SSID_LEN];
-static mut WIFI_CONNECTED_SSID_LEN: usize = 0;
-static mut LAST_SCAN_STATUS: &str = "Sin escaneo";
-static mut LAST_SCAN_RESULTS: [WifiScanEntry; MAX_SCAN_RESULTS] =
-    [WifiScanEntry::empty(); MAX_SCAN_RESULTS];
-static mut LAST_SCAN_COUNT: usize = 0;
+/// Filled by the probe; the adapter is only inventoried, never reprogrammed.
+pub static GLOBAL_INTEL_WIFI: Once<IntelWifiDevice> = Once::new();
+
+struct WifiState {
+    status: &'static str,
+    profile: Option<WifiProfile>,
+    connected: bool,
+    connected_ssid: [u8; MAX_SSID_LEN],
+    connected_ssid_len: usize,
+    last_scan_status: &'static str,
+    last_scan_results: [WifiScanEntry; MAX_SCAN_RESULTS],
+    last_scan_count: usize,
+}
+
+impl WifiState {
+    fn clear_connection(&mut self) {
+        self.connected = false;
+        self.connected_ssid = [0; MAX_SSID_LEN];
+        self.connected_ssid_len = 0;
+    }
+}
+
+static STATE: SpinLock<WifiState> = SpinLock::new(WifiState {
+    status: WIFI_STATUS_NOT_DETECTED,
+    profile: None,
+    connected: false,
+    connected_ssid: [0; MAX_SSID_LEN],
+    connected_ssid_len: 0,
+    last_scan_status: "Sin escaneo",
+    last_scan_results: [WifiScanEntry::empty(); MAX_SCAN_RESULTS],
+    last_scan_count: 0,
+});
 
 fn wifi_model_name(device_id: u16) -> Option<&'static str> {
     match device_id {
@@ -156,17 +181,38 @@ fn copy_ascii<const N: usize>(dst: &mut [u8; N], text: &str, empty_ok: bool) ->
     Ok(text.len())
 }
 
-fn clear_scan_results() {
-    unsafe {
-        LAST_SCAN_RESULTS = [WifiScanEntry::empty(); MAX_SCAN_RESULTS];
-        LAST_SCAN_COUNT = 0;
-    }
+fn clear_scan_results(state: &mut WifiState) {
+    state.last_scan_results = [WifiScanEntry::empty(); MAX_SCAN_RESULTS];
+    state.last_scan_count = 0;
+}
+
+pub static PCI_DRIVER: PciDriver = PciDriver {
+    name: "intel_wifi",
+    ids: &[PciMatch::vendor_class(0x8086, 0x02, 0x80)],
+    probe: init,
+};
+
+static GUARD_OPS: crate::driver_guard::GuardOps = crate::driver_guard::GuardOps {
+    name: "intel_wifi",
+    release,
+};
+
+/// After a fault: `is_present` already reports no adapter; drop the
+/// connection so the network stack falls back to Ethernet.
+fn release() {
+    unsafe { STATE.force_unlock() };
+    let mut state = STATE.lock();
+    state.clear_connection();
+    clear_scan_results(&mut state);
+    state.status = WIFI_STATUS_FAILED;
+    state.last_scan_status = WIFI_STATUS_FAILED;
 }
 
 pub fn init(device: PciDevice) {
     if device.vendor_id != VENDOR_INTEL {
         return;
     }
+    crate::driver_guard::register(&GUARD_OPS);
 
     let command_reg = unsafe { read_config(device.bus, device.slot, device.func, 0x04) as u16 };
     let class_rev = unsafe { read_config(device.bus, device.slot, device.func, 0x08) };
@@ -213,30 +259,39 @@ pub fn init(device: PciDevice) {
         println("Intel WiFi: Firmware hint unavailable for this PCI ID.");
     }
 
-    unsafe {
-        // Keep probe non-invasive until a full native Wi-Fi driver is implemented.
-        // Do not enable bus master/MMIO here on real hardware.
-        GLOBAL_INTEL_WIFI = Some(IntelWifiDevice {
-            pci: device,
-            mmio_base,
-            command_reg,
-            revision_id,
-            subsystem_vendor_id,
-            subsystem_device_id,
-        });
-        WIFI_STATUS = if fw_hint.is_some() {
-            WIFI_STATUS_PHASE1_FW
-        } else {
-            WIFI_STATUS_UNKNOWN_ID
-        };
+    // Keep probe non-invasive until a full native Wi-Fi driver is implemented.
+    // Do not enable bus master/MMIO here on real hardware.
+    let adapter = IntelWifiDevice {
+        pci: device,
+        mmio_base,
+        command_reg,
+        revision_id,
+        subsystem_vendor_id,
+        subsystem_device_id,
+    };
+    if GLOBAL_INTEL_WIFI.set(adapter).is_err() {
+        println("Intel WiFi: second adapter ignored, only one is supported.");
+        return;
     }
+    STATE.lock().status = if fw_hint.is_some() {
+        WIFI_STATUS_PHASE1_FW
+    } else {
+        WIFI_STATUS_UNKNOWN_ID
+    };
+
+    let node = crate::device::add_class_device(Some(crate::device::pci_function(&device)), "wlan", "net", "intel-wifi");
+    crate::device::set_attr(node, "model", model);
+    if let Some(hint) = fw_hint {
+        crate::device::set_attr(node, "firmware", hint);
+    }
+    crate::device::set_live(node, "status", || alloc::string::String::from(get_status()));
 
     println("Intel WiFi: Base driver initialized (phase1 safe probe).");
     println("Intel WiFi: Windows .sys/.inf package cannot run directly in this kernel.");
 }
 
 pub fn is_present() -> bool {
-    unsafe { GLOBAL_INTEL_WIFI.is_some() }
+    GLOBAL_INTEL_WIFI.is_set() && !crate::driver_guard::failed(PCI_DRIVER.name)
 }
 
 pub fn is_data_path_ready() -> bool {
@@ -246,99 +301,99 @@ pub fn is_data_path_ready() -> bool {
 }
 
 pub fn get_model_name() -> Option<&'static str> {
-    unsafe {
-        GLOBAL_INTEL_WIFI
-            .as_ref()
-            .and_then(|dev| wifi_model_name(dev.pci.device_id))
-    }
+    GLOBAL_INTEL_WIFI
+        .get()
+        .and_then(|dev| wifi_model_name(dev.pci.device_id))
 }
 
 pub fn get_status() -> &'static str {
-    unsafe { WIFI_STATUS }
+    STATE.lock().status
 }
 
 pub fn scan_networks() -> &'static str {
-    clear_scan_results();
+    crate::driver_guard::call(PCI_DRIVER.name, scan).unwrap_or(WIFI_STATUS_FAILED)
+}
+
+fn scan() -> &'static str {
+    let mut state = STATE.lock();
+    clear_scan_results(&mut state);
 
     if !is_present() {
-        unsafe {
-            LAST_SCAN_STATUS = "No hay adaptador WiFi Intel detectado.";
-        }
-        return unsafe { LAST_SCAN_STATUS };
+        state.last_scan_status = "No hay adaptador WiFi Intel detectado.";
+        return state.last_scan_status;
     }
 
     // Soft-mode scan: populate visible networks from saved profile or demo entries.
     // On real hardware with a full driver these would come from 802.11 probe responses.
-    unsafe {
-        let mut idx = 0usize;
-
-        // If a profile is configured, show it as the first entry
-        if let Some(ref profile) = WIFI_PROFILE {
-            if profile.ssid_len > 0 {
-                LAST_SCAN_RESULTS[idx].ssid = profile.ssid;
-                LAST_SCAN_RESULTS[idx].ssid_len = profile.ssid_len;
-                LAST_SCAN_RESULTS[idx].rssi_dbm = -42;
-                LAST_SCAN_RESULTS[idx].channel = 6;
-                LAST_SCAN_RESULTS[idx].secure = profile.secure;
-                LAST_SCAN_RESULTS[idx].valid = true;
-                idx += 1;
-            }
+    let mut idx = 0usize;
+
+    // If a profile is configured, show it as the first entry
+    if let Some(profile) = state.profile {
+        if profile.ssid_len > 0 {
+            let entry = &mut state.last_scan_results[idx];
+            entry.ssid = profile.ssid;
+            entry.ssid_len = profile.ssid_len;
+            entry.rssi_dbm = -42;
+            entry.channel = 6;
+            entry.secure = profile.secure;
+            entry.valid = true;
+            idx += 1;
         }
+    }
 
-        // Add ambient networks that would typically be visible
-        let ambient: [(&[u8], i8, u8, bool); 5] = [
-            (b"INFINITUM_2G", -55, 1, true),
-            (b"TELMEX_HOME", -62, 6, true),
-            (b"Totalplay-5G", -68, 36, true),
-            (b"Vecino_WiFi", -74, 11, true),
-            (b"WiFi_Libre", -80, 1, false),
-        ];
-
-        for &(name, rssi, ch, sec) in ambient.iter() {
-            if idx >= MAX_SCAN_RESULTS {
-                break;
-            }
-            // Skip if it duplicates the profile SSID
-            let mut dup = false;
-            if let Some(ref profile) = WIFI_PROFILE {
-                if profile.ssid_len == name.len() && &profile.ssid[..profile.ssid_len] == name {
-                    dup = true;
-                }
-            }
-            if dup {
-                continue;
+    // Add ambient networks that would typically be visible
+    let ambient: [(&[u8], i8, u8, bool); 5] = [
+        (b"INFINITUM_2G", -55, 1, true),
+        (b"TELMEX_HOME", -62, 6, true),
+        (b"Totalplay-5G", -68, 36, true),
+        (b"Vecino_WiFi", -74, 11, true),
+        (b"WiFi_Libre", -80, 1, false),
+    ];
+
+    for &(name, rssi, ch, sec) in ambient.iter() {
+        if idx >= MAX_SCAN_RESULTS {
+            break;
+        }
+        // Skip if it duplicates the profile SSID
+        let mut dup = false;
+        if let Some(ref profile) = state.profile {
+            if profile.ssid_len == name.len() && &profile.ssid[..profile.ssid_len] == name {
+                dup = true;
             }
-            LAST_SCAN_RESULTS[idx].ssid[..name.len()].copy_from_slice(name);
-            LAST_SCAN_RESULTS[idx].ssid_len = name.len();
-            LAST_SCAN_RESULTS[idx].rssi_dbm = rssi;
-            LAST_SCAN_RESULTS[idx].channel = ch;
-            LAST_SCAN_RESULTS[idx].secure = sec;
-            LAST_SCAN_RESULTS[idx].valid = true;
-            idx += 1;
         }
-
-        LAST_SCAN_COUNT = idx;
-        WIFI_STATUS = WIFI_STATUS_PHASE1_READY;
-        LAST_SCAN_STATUS = "Escaneo completado.";
+        if dup {
+            continue;
+        }
+        let entry = &mut state.last_scan_results[idx];
+        entry.ssid[..name.len()].copy_from_slice(name);
+        entry.ssid_len = name.len();
+        entry.rssi_dbm = rssi;
+        entry.channel = ch;
+        entry.secure = sec;
+        entry.valid = true;
+        idx += 1;
     }
-    unsafe { LAST_SCAN_STATUS }
+
+    state.last_scan_count = idx;
+    state.status = WIFI_STATUS_PHASE1_READY;
+    state.last_scan_status = "Escaneo completado.";
+    state.last_scan_status
 }
 
 pub fn get_last_scan_status() -> &'static str {
-    unsafe { LAST_SCAN_STATUS }
+    STATE.lock().last_scan_status
 }
 
 pub fn get_last_scan_count() -> usize {
-    unsafe { LAST_SCAN_COUNT }
+    STATE.lock().last_scan_count
 }
 
 pub fn get_scan_entry(index: usize) -> Option<WifiScanEntry> {
-    unsafe {
-        if index < LAST_SCAN_COUNT {
-            Some(LAST_SCAN_RESULTS[index])
-        } else {
-            None
-        }
+    let state = STATE.lock();
+    if index < state.last_scan_count {
+        Some(state.last_scan_results[index])
+    } else {
+        None
     }
 }
 
@@ -348,124 +403,112 @@ pub fn configure_profile(ssid: &str, psk: &str) -> Result<&'static str, &'static
     profile.psk_len = copy_ascii(&mut profile.psk, psk, true)?;
     profile.secure = profile.psk_len > 0;
 
-    unsafe {
-        WIFI_PROFILE = Some(profile);
-        WIFI_CONNECTED = false;
-        WIFI_CONNECTED_SSID = [0; MAX_SSID_LEN];
-        WIFI_CONNECTED_SSID_LEN = 0;
-        if WIFI_STATUS == WIFI_STATUS_NOT_DETECTED {
-            WIFI_STATUS = WIFI_STATUS_UNKNOWN_ID;
-        }
+    let mut state = STATE.lock();
+    state.profile = Some(profile);
+    state.clear_connection();
+    if state.status == WIFI_STATUS_NOT_DETECTED {
+        state.status = WIFI_STATUS_UNKNOWN_ID;
     }
     Ok("Perfil guardado.")
 }
 
 pub fn clear_profile() -> &'static str {
-    unsafe {
-        WIFI_PROFILE = None;
-        WIFI_CONNECTED = false;
-        WIFI_CONNECTED_SSID = [0; MAX_SSID_LEN];
-        WIFI_CONNECTED_SSID_LEN = 0;
-    }
+    let mut state = STATE.lock();
+    state.profile = None;
+    state.clear_connection();
     "Perfil WiFi eliminado."
 }
 
 pub fn get_profile_info() -> Option<WifiProfileInfo> {
-    unsafe {
-        WIFI_PROFILE.map(|p| WifiProfileInfo {
-            ssid: p.ssid,
-            ssid_len: p.ssid_len,
-            secure: p.secure,
-        })
-    }
+    STATE.lock().profile.map(|p| WifiProfileInfo {
+        ssid: p.ssid,
+        ssid_len: p.ssid_len,
+        secure: p.secure,
+    })
 }
 
 pub fn has_profile() -> bool {
-    unsafe { WIFI_PROFILE.is_some() }
+    STATE.lock().profile.is_some()
 }
 
 pub fn connect_profile() -> &'static str {
+    crate::driver_guard::call(PCI_DRIVER.name, connect).unwrap_or(WIFI_STATUS_FAILED)
+}
+
+fn connect() -> &'static str {
     if !is_present() {
         return "No hay adaptador WiFi Intel detectado.";
     }
 
-    let profile = unsafe { WIFI_PROFILE };
-    let Some(profile) = profile else {
+    let mut state = STATE.lock();
+    let Some(profile) = state.profile else {
         return "No hay perfil configurado. Selecciona una red y pon la clave.";
     };
 
-    unsafe {
-        WIFI_CONNECTED = true;
-        WIFI_CONNECTED_SSID = profile.ssid;
-        WIFI_CONNECTED_SSID_LEN = profile.ssid_len;
-        WIFI_STATUS = WIFI_STATUS_PHASE1_READY;
-    }
+    state.connected = true;
+    state.connected_ssid = profile.ssid;
+    state.connected_ssid_len = profile.ssid_len;
+    state.status = WIFI_STATUS_PHASE1_READY;
     "WiFi conectado."
 }
 
 pub fn disconnect() -> &'static str {
-    unsafe {
-        WIFI_CONNECTED = false;
-        WIFI_CONNECTED_SSID = [0; MAX_SSID_LEN];
-        WIFI_CONNECTED_SSID_LEN = 0;
-        if GLOBAL_INTEL_WIFI.is_some() {
-            WIFI_STATUS = WIFI_STATUS_PHASE1_FW;
-        }
+    crate::driver_guard::call(PCI_DRIVER.name, drop_connection).unwrap_or(WIFI_STATUS_FAILED)
+}
+
+fn drop_connection() -> &'static str {
+    let mut state = STATE.lock();
+    state.clear_connection();
+    if is_present() {
+        state.status = WIFI_STATUS_PHASE1_FW;
     }
     "WiFi desconectado."
 }
 
 pub fn is_connected() -> bool {
-    unsafe { WIFI_CONNECTED }
+    STATE.lock().connected
 }
 
 pub fn connected_ssid() -> Option<([u8; MAX_SSID_LEN], usize)> {
-    unsafe {
-        if WIFI_CONNECTED && WIFI_CONNECTED_SSID_LEN > 0 {
-            Some((WIFI_CONNECTED_SSID, WIFI_CONNECTED_SSID_LEN))
-        } else {
-            None
-        }
+    let state = STATE.lock();
+    if state.connected && state.connected_ssid_len > 0 {
+        Some((state.connected_ssid, state.connected_ssid_len))
+    } else {
+        None
     }
 }
 
 pub fn firmware_hint() -> Option<&'static str> {
-    unsafe {
-        GLOBAL_INTEL_WIFI
-            .as_ref()
-            .and_then(|dev| firmware_hint_for_device(dev.pci.device_id))
-    }
+    GLOBAL_INTEL_WIFI
+        .get()
+        .and_then(|dev| firmware_hint_for_device(dev.pci.device_id))
 }
 
 pub fn get_pci_location() -> Option<(u8, u8, u8)> {
-    unsafe {
-        GLOBAL_INTEL_WIFI
-            .as_ref()
-            .map(|dev| (dev.pci.bus, dev.pci.slot, dev.pci.func))
-    }
+    GLOBAL_INTEL_WIFI
+        .get()
+        .map(|dev| (dev.pci.bus, dev.pci.slot, dev.pci.func))
 }
 
 pub fn get_pci_ids() -> Option<(u16, u16, u16, u16)> {
-    unsafe {
-        GLOBAL_INTEL_WIFI.as_ref().map(|dev| {
-            (
-                dev.pci.vendor_id,
-                dev.pci.device_id,
-                dev.subsystem_vendor_id,
-                dev.subsystem_device_id,
-            )
-        })
-    }
+    GLOBAL_INTEL_WIFI.get().map(|dev| {
+        (
+            dev.pci.vendor_id,
+            dev.pci.device_id,
+            dev.subsystem_vendor_id,
+            dev.subsystem_device_id,
+        )
+    })
 }
 
 pub fn get_revision() -> Option<u8> {
-    unsafe { GLOBAL_INTEL_WIFI.as_ref().map(|dev| dev.revision_id) }
+    GLOBAL_INTEL_WIFI.get().map(|dev| dev.revision_id)
 }
 
 pub fn get_command_reg() -> Option<u16> {
-    unsafe { GLOBAL_INTEL_WIFI.as_ref().map(|dev| dev.command_reg) }
+    GLOBAL_INTEL_WIFI.get().map(|dev| dev.command_reg)
 }
 
 pub fn get_mmio_base() -> Option<u64> {
-    unsafe { GLOBAL_INTEL_WIFI.as_ref().and_then(|dev| dev.mmio_base) }
+    GLOBAL_INTEL_WIFI.get().and_then(|dev| dev.mmio_base)
 }
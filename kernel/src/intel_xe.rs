@@ -1,5 +1,8 @@
-use crate::pci::{PciDevice, read_bar};
+use crate::pci::{PciDevice, PciDriver, PciMatch, PCI_ANY_CLASS, read_bar};
 use crate::println;
+use crate::spinlock::SpinLock;
+use crate::sync::Once;
+use core::sync::atomic::{AtomicU64, Ordering};
 
 // Intel Vendor ID
 const VENDOR_INTEL: u16 = 0x8086;
@@ -26,6 +29,12 @@ const GTT_OFFSET: u32 = 0x40000;
 const GTT_PAGE_SIZE: u32 = 4096;
 const GTT_PTE_PRESENT: u64 = 0x01;
 
+// Display pipe A color management (Gen11+)
+const LGC_PALETTE_A: u32 = 0x4A000; // 256 x (R << 16 | G << 8 | B)
+const GAMMA_MODE_A: u32 = 0x4A480;
+const GAMMA_MODE_8BIT: u32 = 0x00;
+const GAMMA_MODE_POST_CSC_ENABLE: u32 = 1 << 31;
+
 pub struct GttManager {
     mmio_base: u64,
 }
@@ -74,6 +83,10 @@ pub struct RingBuffer {
     tail: u32,
 }
 
+// SAFETY: `cpu_ptr` is a frame owned by this ring alone; every access goes
+// through `BCS_RING`'s lock.
+unsafe impl Send for RingBuffer {}
+
 impl RingBuffer {
     pub unsafe fn new(mmio_base: u64, engine_base: u32) -> Self {
         let size = 4096; // 1 page
@@ -131,8 +144,51 @@ impl RingBuffer {
     }
 }
 
-static mut BCS_RING: Option<RingBuffer> = None;
-static mut GTT: Option<GttManager> = None;
+static BCS_RING: SpinLock<Option<RingBuffer>> = SpinLock::new(None);
+static GTT: Once<GttManager> = Once::new();
+static MMIO_BASE: AtomicU64 = AtomicU64::new(0);
+static XE_DEVICE: Once<PciDevice> = Once::new();
+
+static PM_OPS: crate::pm::PmOps = crate::pm::PmOps {
+    name: "intel_xe",
+    suspend,
+    resume,
+};
+
+/// Let the blitter drain what was already submitted.
+fn suspend() -> Result<(), &'static str> {
+    if let Some(ring) = BCS_RING.lock().as_ref() {
+        for _ in 0..1000 {
+            if unsafe { ring.streamer.read_head() } & 0x001F_FFFC == ring.tail {
+                return Ok(());
+            }
+            uefi::boot::stall(100);
+        }
+        return Err("el blitter no termino");
+    }
+    Ok(())
+}
+
+/// The ring registers and the pipe palette are lost in S3: enable the
+/// device again, restart the ring empty and reload the gamma tables.
+fn resume() {
+    unsafe {
+        if let Some(device) = XE_DEVICE.get() {
+            crate::pci::enable_bus_master(device.bus, device.slot, device.func);
+        }
+        if let Some(ring) = BCS_RING.lock().as_mut() {
+            ring.tail = 0;
+            ring.init_hardware();
+        }
+    }
+    crate::gamma::load_config();
+}
+
+pub static PCI_DRIVER: PciDriver = PciDriver {
+    name: "intel_xe",
+    ids: &[PciMatch::vendor_class(0x8086, 0x03, PCI_ANY_CLASS)],
+    probe: init,
+};
 
 pub fn init(device: PciDevice) {
     if device.vendor_id != VENDOR_INTEL {
@@ -147,10 +203,12 @@ pub fn init(device: PciDevice) {
     if let Some(addr) = mmio_base {
         println("Intel Xe: GTTMMAD (MMIO) Initialized.");
         
-        unsafe {
-            GTT = Some(GttManager::new(addr));
-            BCS_RING = Some(RingBuffer::new(addr, BCS_BASE));
-        }
+        MMIO_BASE.store(addr, Ordering::Release);
+        let _ = GTT.set(GttManager::new(addr));
+        let ring = unsafe { RingBuffer::new(addr, BCS_BASE) };
+        *BCS_RING.lock() = Some(ring);
+        let _ = XE_DEVICE.set(device);
+        crate::pm::register(&PM_OPS);
 
         println("Intel Xe: Hardware Acceleration Ready (GTT + BCS Ring).");
     }
@@ -158,7 +216,7 @@ pub fn init(device: PciDevice) {
 
 pub fn blit(src_phys: u64, dst_phys: u64, x: u32, y: u32, w: u32, h: u32, pitch: u32) -> bool {
     unsafe {
-        if let Some(ref mut ring) = BCS_RING {
+        if let Some(ring) = BCS_RING.lock().as_mut() {
             // XY_SRC_COPY_BLT (Gen12 command)
             // Note: In real Gen12, we must use Global GTT offsets.
             // For this pilot, we assume physical mapping is 1:1 or handled via GTT.
@@ -180,3 +238,35 @@ pub fn blit(src_phys: u64, dst_phys: u64, x: u32, y: u32, w: u32, h: u32, pitch:
     }
     false
 }
+
+/// Load a per-channel 8-bit ramp into the pipe A legacy palette and enable
+/// post-CSC gamma. Returns false when no Xe display engine is mapped.
+pub fn set_gamma_lut(r: &[u8; 256], g: &[u8; 256], b: &[u8; 256]) -> bool {
+    let base = MMIO_BASE.load(Ordering::Acquire);
+    if base == 0 {
+        return false;
+    }
+    unsafe {
+        for i in 0..256usize {
+            let entry = (r[i] as u32) << 16 | (g[i] as u32) << 8 | b[i] as u32;
+            let reg = (base + LGC_PALETTE_A as u64 + (i * 4) as u64) as *mut u32;
+            core::ptr::write_volatile(reg, entry);
+        }
+        let mode = (base + GAMMA_MODE_A as u64) as *mut u32;
+        core::ptr::write_volatile(mode, GAMMA_MODE_8BIT | GAMMA_MODE_POST_CSC_ENABLE);
+    }
+    true
+}
+
+/// Turn pipe A gamma back off. Returns false when no Xe display engine is mapped.
+pub fn clear_gamma_lut() -> bool {
+    let base = MMIO_BASE.load(Ordering::Acquire);
+    if base == 0 {
+        return false;
+    }
+    unsafe {
+        let mode = (base + GAMMA_MODE_A as u64) as *mut u32;
+        core::ptr::write_volatile(mode, GAMMA_MODE_8BIT);
+    }
+    true
+}
@@ -46,6 +46,11 @@ impl IdtEntry {
             zero: 0,
         }
     }
+
+    const fn with_ist(mut self, ist: u8) -> Self {
+        self.ist = ist;
+        self
+    }
 }
 
 #[inline]
@@ -83,18 +88,30 @@ impl IdtSummary {
 static mut IDT: [IdtEntry; 256] = [IdtEntry::missing(); 256];
 static mut SUMMARY: IdtSummary = IdtSummary::empty();
 static IRQ0_COUNT: AtomicU64 = AtomicU64::new(0);
+/// IST slot of the double-fault gate: 0 until every TSS has a stack there.
+static DOUBLE_FAULT_IST: AtomicU8 = AtomicU8::new(0);
 static APIC_TIMER_MODE: AtomicU8 = AtomicU8::new(0);
 static APIC_TIMER_BASE: AtomicU64 = AtomicU64::new(0);
 static PIC_TIMER_ARMED: AtomicBool = AtomicBool::new(false);
 static IRQ_TIMER_SUSPEND_DEPTH: AtomicU32 = AtomicU32::new(0);
 static SAVED_APIC_LVT_TIMER: AtomicU32 = AtomicU32::new(0);
 static SAVED_PIC_IMR: AtomicU8 = AtomicU8::new(0xFF);
+/// TSC cycles per tick while the LAPIC runs in TSC-deadline mode, 0 when it
+/// is periodic (or off).
+static TSC_DEADLINE_TICK: AtomicU64 = AtomicU64::new(0);
+static NEXT_TICK_TSC: AtomicU64 = AtomicU64::new(0);
+/// Earliest timer-wheel deadline in TSC cycles, 0 if none.
+static WAKEUP_TSC: AtomicU64 = AtomicU64::new(0);
+/// Deadline interrupts that arrived a whole period or more late.
+static LATE_TICKS: AtomicU64 = AtomicU64::new(0);
 
 const APIC_MODE_NONE: u8 = 0;
 const APIC_MODE_XAPIC: u8 = 1;
 const APIC_MODE_X2APIC: u8 = 2;
 
 const APIC_TIMER_VECTOR: u8 = 33;
+const DOUBLE_FAULT_VECTOR: usize = 8;
+const PAGE_FAULT_VECTOR: u64 = 14;
 pub const IPI_RESCHED_VECTOR: u8 = 0xF0;
 const IA32_APIC_BASE_MSR: u32 = 0x1B;
 const IA32_X2APIC_EOI: u32 = 0x80B;
@@ -102,6 +119,7 @@ const IA32_X2APIC_SVR: u32 = 0x80F;
 const IA32_X2APIC_LVT_TIMER: u32 = 0x832;
 const IA32_X2APIC_INITIAL_COUNT: u32 = 0x838;
 const IA32_X2APIC_DIVIDE: u32 = 0x83E;
+const IA32_TSC_DEADLINE: u32 = 0x6E0;
 const APIC_BASE_ENABLE: u64 = 1 << 11;
 const APIC_BASE_X2APIC_ENABLE: u64 = 1 << 10;
 const APIC_BASE_ADDR_MASK: u64 = 0xFFFF_F000;
@@ -113,6 +131,7 @@ const APIC_REG_DIVIDE: u64 = 0x03E0;
 const APIC_SVR_ENABLE: u32 = 1 << 8;
 const APIC_SVR_SPURIOUS_VECTOR: u32 = 0xFF;
 const APIC_LVT_PERIODIC: u32 = 1 << 17;
+const APIC_LVT_TSC_DEADLINE: u32 = 2 << 17;
 const APIC_LVT_MASKED: u32 = 1 << 16;
 const APIC_DIVIDE_BY_16: u32 = 0x3;
 const ALLOW_XAPIC_TIMER_MMIO: bool = false;
@@ -127,6 +146,7 @@ unsafe extern "C" {
     fn ss_stub();
     fn gp_stub();
     fn pf_stub();
+    fn df_stub();
     fn mf_stub();
     fn ac_stub();
     fn xm_stub();
@@ -313,6 +333,40 @@ linux_fault_err_entry:
     push r14
     push r15
 
+    // A #PF inside uaccess_copy/uaccess_clear resumes after the string
+    // instruction; the helper then reports the bytes it could not move.
+    cmp r15, 14
+    jne .Lfault_no_fixup
+    mov rax, [rsp + 128]
+    lea rcx, [rip + uaccess_copy_insn]
+    lea rdx, [rip + uaccess_copy_done]
+    cmp rax, rcx
+    je .Lfault_fixup
+    lea rcx, [rip + uaccess_clear_insn]
+    lea rdx, [rip + uaccess_clear_done]
+    cmp rax, rcx
+    jne .Lfault_no_fixup
+.Lfault_fixup:
+    mov [rsp + 128], rdx
+    pop r15
+    pop r14
+    pop r13
+    pop r12
+    pop r11
+    pop r10
+    pop r9
+    pop r8
+    pop rdi
+    pop rsi
+    pop rbp
+    pop rbx
+    pop rdx
+    pop rcx
+    pop rax
+    add rsp, 8
+    iretq
+
+.Lfault_no_fixup:
     cmp byte ptr [rip + LINUX_REAL_SLICE_ACTIVE], 0
     je .Lfault_halt
     mov rax, [rsp + 136] // interrupted CS from hardware frame (+errcode)
@@ -394,6 +448,39 @@ linux_fault_err_entry:
     jmp qword ptr [rip + LINUX_REAL_SLICE_RETURN_RIP]
 
 .Lfault_halt:
+    // Kernel fault outside a Linux slice: report and stop.
+    mov rdi, r15
+    mov rsi, rsp
+    and rsp, -16
+    call kernel_fault_entry
+    cli
+1:
+    hlt
+    jmp 1b
+
+// Runs on IST 1 once `use_double_fault_stack` has set it up, so a kernel
+// stack overflow (a #PF that cannot push its frame) still gets here.
+.global df_stub
+df_stub:
+    push rax
+    push rcx
+    push rdx
+    push rbx
+    push rbp
+    push rsi
+    push rdi
+    push r8
+    push r9
+    push r10
+    push r11
+    push r12
+    push r13
+    push r14
+    push r15
+    mov rdi, 8
+    mov rsi, rsp
+    and rsp, -16
+    call kernel_fault_entry
     cli
 1:
     hlt
@@ -722,9 +809,13 @@ kernel_preempt_trampoline:
 
 #[unsafe(no_mangle)]
 extern "C" fn irq0_rust() {
+    crate::driver_guard::irq_enter();
     let apic_timer_active = APIC_TIMER_MODE.load(Ordering::SeqCst) != APIC_MODE_NONE;
     let pic_timer_active = PIC_TIMER_ARMED.load(Ordering::SeqCst);
-    if apic_timer_active || pic_timer_active {
+    // In TSC-deadline mode the interrupt is either the emulated periodic
+    // tick or an early wakeup for the timer wheel.
+    let tick_due = TSC_DEADLINE_TICK.load(Ordering::SeqCst) == 0 || tsc_deadline_service();
+    if (apic_timer_active || pic_timer_active) && tick_due {
         IRQ0_COUNT.fetch_add(1, Ordering::SeqCst);
         crate::timer::irq_tick();
         crate::process::irq_preempt_signal();
@@ -737,11 +828,14 @@ extern "C" fn irq0_rust() {
         outb(0xA0, 0x20);
         outb(0x20, 0x20);
     }
+    crate::driver_guard::irq_exit();
 }
 
 #[unsafe(no_mangle)]
 extern "C" fn ipi_resched_rust() {
+    crate::driver_guard::irq_enter();
     apic_eoi_if_present();
+    crate::driver_guard::irq_exit();
 }
 
 #[inline]
@@ -767,6 +861,7 @@ pub fn init_skeleton() -> IdtSummary {
     let mf_handler = mf_stub as *const () as usize as u64;
     let ac_handler = ac_stub as *const () as usize as u64;
     let xm_handler = xm_stub as *const () as usize as u64;
+    let df_handler = df_stub as *const () as usize as u64;
     let code_selector = current_cs();
 
     unsafe {
@@ -782,6 +877,8 @@ pub fn init_skeleton() -> IdtSummary {
         IDT[1] = IdtEntry::from_handler(debug_handler, code_selector);
         // Recover user-mode real-slice faults instead of halting whole GUI.
         IDT[6] = IdtEntry::from_handler(ud_handler, code_selector);
+        IDT[DOUBLE_FAULT_VECTOR] = IdtEntry::from_handler(df_handler, code_selector)
+            .with_ist(DOUBLE_FAULT_IST.load(Ordering::Relaxed));
         IDT[7] = IdtEntry::from_handler(nm_handler, code_selector);
         IDT[10] = IdtEntry::from_handler(ts_handler, code_selector);
         IDT[11] = IdtEntry::from_handler(np_handler, code_selector);
@@ -835,6 +932,77 @@ pub fn init_with_irq0() -> IdtSummary {
     s
 }
 
+/// Point the double-fault gate at IST 1, once the BSP's TSS has a stack
+/// there (APs get theirs before they start). While Boot Services run the BSP
+/// still has the firmware IDT loaded, so its double-fault gate is redirected
+/// too.
+pub fn use_double_fault_stack() {
+    DOUBLE_FAULT_IST.store(1, Ordering::Relaxed);
+    let code_selector = current_cs();
+    let entry = IdtEntry::from_handler(df_stub as *const () as usize as u64, code_selector).with_ist(1);
+    unsafe {
+        IDT[DOUBLE_FAULT_VECTOR] = entry;
+        let mut live = IdtPointer { limit: 0, base: 0 };
+        asm!("sidt [{}]", in(reg) &mut live as *mut IdtPointer, options(nostack, preserves_flags));
+        let live_base = live.base;
+        let ours = (core::ptr::addr_of!(IDT) as *const _) as u64;
+        if live_base != 0 && live_base != ours && live.limit as usize >= (DOUBLE_FAULT_VECTOR + 1) * 16 - 1 {
+            core::ptr::write_volatile((live_base as *mut IdtEntry).add(DOUBLE_FAULT_VECTOR), entry);
+        }
+    }
+}
+
+/// Kernel-mode faults outside a Linux slice and every double fault end here.
+/// A fault inside a `driver_guard` domain resumes there instead.
+/// `frame` points at the registers the stub pushed (r15 first), then the
+/// hardware frame. A fault on a stack guard page is a stack overflow.
+#[no_mangle]
+extern "C" fn kernel_fault_entry(vector: u64, frame: *const u64) -> ! {
+    let has_error = matches!(vector, 8 | 10..=14 | 17);
+    let hw = if has_error { 16 } else { 15 };
+    let (error, rip, rsp) = unsafe {
+        (
+            if has_error { *frame.add(15) } else { 0 },
+            *frame.add(hw),
+            *frame.add(hw + 3),
+        )
+    };
+    let cr2: u64;
+    unsafe {
+        asm!("mov {}, cr2", out(reg) cr2, options(nomem, nostack, preserves_flags));
+    }
+    // Recorded before anything else runs: the heap may be what faulted.
+    crate::klog::klog_irq!(
+        crate::klog::Level::Critical,
+        "excepcion {} (error {:#x}) en rip {:#x}, rsp {:#x}, cr2 {:#x}",
+        vector,
+        error,
+        rip,
+        rsp,
+        cr2
+    );
+    // Inside a driver entry point: that driver is disabled, the kernel goes on.
+    crate::driver_guard::recover_exception(vector, error, rip, cr2);
+    let faulting_page = vector == PAGE_FAULT_VECTOR || vector == DOUBLE_FAULT_VECTOR as u64;
+    let owner = faulting_page
+        .then(|| crate::stack_guard::owner_at(cr2))
+        .flatten()
+        .or_else(|| crate::stack_guard::owner_at(rsp));
+    if let Some(owner) = owner {
+        panic!(
+            "stack overflow in task {} (rip {:#x}, rsp {:#x}, cr2 {:#x})",
+            crate::stack_guard::owner_label(owner),
+            rip,
+            rsp,
+            cr2
+        );
+    }
+    panic!(
+        "kernel exception {} (error {:#x}) at rip {:#x}, rsp {:#x}, cr2 {:#x}",
+        vector, error, rip, rsp, cr2
+    );
+}
+
 pub fn install_user_gate(vector: u8, handler_addr: u64) {
     let code_selector = current_cs();
     unsafe {
@@ -903,6 +1071,7 @@ pub fn irq_timer_source_name() -> &'static str {
         "pic-pit"
     } else {
         match APIC_TIMER_MODE.load(Ordering::SeqCst) {
+            APIC_MODE_X2APIC if TSC_DEADLINE_TICK.load(Ordering::SeqCst) != 0 => "apic-tsc-deadline",
             APIC_MODE_X2APIC => "apic-x2apic",
             APIC_MODE_XAPIC => "apic-xapic",
             _ => "none",
@@ -970,6 +1139,8 @@ pub fn resume_irq_timer() {
         if lvt != 0 {
             unsafe { crate::hal::wrmsr(IA32_X2APIC_LVT_TIMER, lvt as u64); }
         }
+        // A deadline that passed while masked is dropped, not delivered.
+        arm_tsc_deadline();
         return;
     }
 
@@ -1059,6 +1230,81 @@ fn apic_eoi_if_present() {
     }
 }
 
+/// Advance the emulated tick and re-arm the deadline. Returns whether a
+/// tick period has elapsed.
+fn tsc_deadline_service() -> bool {
+    let period = TSC_DEADLINE_TICK.load(Ordering::SeqCst);
+    let now = crate::hal::rdtsc();
+    let next_tick = NEXT_TICK_TSC.load(Ordering::SeqCst);
+    let tick_due = now >= next_tick;
+    if tick_due {
+        let mut next = next_tick.saturating_add(period);
+        if next <= now {
+            next = now.saturating_add(period);
+            let late = LATE_TICKS.fetch_add(1, Ordering::Relaxed) + 1;
+            if late % 1024 == 1 {
+                crate::klog::klog_irq!(
+                    crate::klog::Level::Warning,
+                    "timer: interrupcion de tick atrasada ({} en total)",
+                    late
+                );
+            }
+        }
+        NEXT_TICK_TSC.store(next, Ordering::SeqCst);
+    }
+    let wakeup = WAKEUP_TSC.load(Ordering::SeqCst);
+    if wakeup != 0 && wakeup <= now {
+        WAKEUP_TSC.store(0, Ordering::SeqCst);
+        // Hand the CPU back to the runtime loop so `timer::run_due` runs.
+        crate::process::irq_preempt_signal();
+    }
+    arm_tsc_deadline();
+    tick_due
+}
+
+fn arm_tsc_deadline() {
+    if TSC_DEADLINE_TICK.load(Ordering::SeqCst) == 0 {
+        return;
+    }
+    let next_tick = NEXT_TICK_TSC.load(Ordering::SeqCst);
+    let at = match WAKEUP_TSC.load(Ordering::SeqCst) {
+        0 => next_tick,
+        wakeup => wakeup.min(next_tick),
+    };
+    unsafe {
+        crate::hal::wrmsr(IA32_TSC_DEADLINE, at);
+    }
+}
+
+/// Earliest timer-wheel deadline, in `timer::now_ms` milliseconds. Only
+/// used in TSC-deadline mode; the periodic modes pick timers up on the next
+/// tick.
+pub fn set_timer_wakeup_ms(deadline_ms: Option<u64>) {
+    let hz = crate::clocksource::tsc_hz();
+    let tsc = match deadline_ms {
+        // Convert relative to now: the wheel clock is not always the TSC.
+        Some(ms) if hz != 0 => {
+            let delta_ms = ms.saturating_sub(crate::timer::now_ms());
+            let cycles = (delta_ms as u128 * hz as u128 / 1000).min(u64::MAX as u128) as u64;
+            crate::hal::rdtsc().saturating_add(cycles).max(1)
+        }
+        _ => 0,
+    };
+    WAKEUP_TSC.store(tsc, Ordering::SeqCst);
+    // Racing the handler is harmless: a stale value at worst fires early
+    // and the handler re-arms from the atomics.
+    arm_tsc_deadline();
+}
+
+/// Cycles per tick for TSC-deadline mode, or 0 when the CPU lacks it or
+/// the TSC has not been calibrated.
+fn tsc_deadline_tick_for_hz(hz: u32) -> u64 {
+    if !cpu_has_tsc_deadline() {
+        return 0;
+    }
+    crate::clocksource::tsc_hz() / hz.clamp(18, 1000) as u64
+}
+
 fn apic_initial_count_for_hz(hz: u32) -> u32 {
     let safe_hz = hz.clamp(18, 1000);
     // Keep first IRQ latency short enough for startup probe on slower timer buses.
@@ -1077,6 +1323,18 @@ fn cpu_has_local_apic() -> bool {
     }
 }
 
+fn cpu_has_tsc_deadline() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let leaf1 = unsafe { __cpuid(1) };
+        (leaf1.ecx & (1 << 24)) != 0
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        false
+    }
+}
+
 fn cpu_has_x2apic() -> bool {
     #[cfg(target_arch = "x86_64")]
     {
@@ -1106,6 +1364,7 @@ pub fn quiesce_firmware_apic() {
         }
     }
 
+    let tsc_deadline_was_armed = TSC_DEADLINE_TICK.swap(0, Ordering::SeqCst) != 0;
     APIC_TIMER_MODE.store(APIC_MODE_NONE, Ordering::SeqCst);
     APIC_TIMER_BASE.store(0, Ordering::SeqCst);
     PIC_TIMER_ARMED.store(false, Ordering::SeqCst);
@@ -1121,6 +1380,9 @@ pub fn quiesce_firmware_apic() {
             return;
         }
         if apic_mode == APIC_MODE_X2APIC {
+            if tsc_deadline_was_armed {
+                crate::hal::wrmsr(IA32_TSC_DEADLINE, 0);
+            }
             crate::hal::wrmsr(IA32_X2APIC_INITIAL_COUNT, 0);
             crate::hal::wrmsr(
                 IA32_X2APIC_LVT_TIMER,
@@ -1173,11 +1435,27 @@ pub fn start_apic_timer_irq(hz: u32) -> bool {
             let svr_new = (svr & !0xFF) | APIC_SVR_SPURIOUS_VECTOR | APIC_SVR_ENABLE;
             crate::hal::wrmsr(IA32_X2APIC_SVR, svr_new as u64);
             crate::hal::wrmsr(IA32_X2APIC_DIVIDE, APIC_DIVIDE_BY_16 as u64);
-            crate::hal::wrmsr(
-                IA32_X2APIC_LVT_TIMER,
-                (APIC_LVT_PERIODIC | APIC_TIMER_VECTOR as u32) as u64,
-            );
-            crate::hal::wrmsr(IA32_X2APIC_INITIAL_COUNT, initial as u64);
+            let tsc_tick = tsc_deadline_tick_for_hz(hz);
+            if tsc_tick != 0 {
+                // One-shot deadlines let the timer wheel wake the CPU between
+                // ticks; the tick itself is re-armed by the handler.
+                crate::hal::wrmsr(
+                    IA32_X2APIC_LVT_TIMER,
+                    (APIC_LVT_TSC_DEADLINE | APIC_TIMER_VECTOR as u32) as u64,
+                );
+                // The SDM asks for a fence between the mode switch and the
+                // first deadline write.
+                core::sync::atomic::fence(Ordering::SeqCst);
+                NEXT_TICK_TSC.store(crate::hal::rdtsc().saturating_add(tsc_tick), Ordering::SeqCst);
+                TSC_DEADLINE_TICK.store(tsc_tick, Ordering::SeqCst);
+                arm_tsc_deadline();
+            } else {
+                crate::hal::wrmsr(
+                    IA32_X2APIC_LVT_TIMER,
+                    (APIC_LVT_PERIODIC | APIC_TIMER_VECTOR as u32) as u64,
+                );
+                crate::hal::wrmsr(IA32_X2APIC_INITIAL_COUNT, initial as u64);
+            }
             APIC_TIMER_BASE.store(0, Ordering::SeqCst);
             APIC_TIMER_MODE.store(APIC_MODE_X2APIC, Ordering::SeqCst);
         } else {
@@ -0,0 +1,772 @@
+//! Kernel log ring buffer.
+//!
+//! Every line printed through `crate::println` is also recorded here with a
+//! sequence number, severity and tick timestamp, so diagnostics survive after
+//! the UEFI console scrolls away and can be replayed by `dmesg` or forwarded
+//! to a remote collector (see `net::syslog`).
+//!
+//! `log` allocates and takes the ring lock, so it must not run in interrupt
+//! handlers. There, `klog_irq!` formats into a [`FixedBuf`] on the stack and
+//! queues the record in a fixed lock-free ring that any core may write. A
+//! `klog-irq` executor
+//! task moves those records into the main ring, and so does every read.
+//!
+//! A timer appends the ring to `\REDUXOS\LOGS\SYSTEM.LOG` and rotates it to
+//! gzip archives by size (`log.file*` settings).
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+
+use crate::spinlock::SpinLock;
+
+const KLOG_MAX_RECORDS: usize = 1024;
+const KLOG_MAX_LINE_BYTES: usize = 240;
+/// Records the IRQ ring holds before the drain task catches up.
+const KLOG_IRQ_SLOTS: usize = 64;
+const KLOG_IRQ_DRAIN_MS: u64 = 100;
+const KLOG_ARCHIVE_DIR: &str = "LOGS";
+const KLOG_ARCHIVE_MAX_FILES: usize = 10_000;
+/// `\REDUXOS\LOGS\SYSTEM.LOG`, the log kept on disk; see [`flush_file`].
+const KLOG_FILE_DIRS: [&str; 2] = ["REDUXOS", "LOGS"];
+const KLOG_FILE_NAME: &str = "SYSTEM.LOG";
+const KLOG_FILE_FLUSH_MS: u64 = 2_000;
+pub const FILE_KEY: &str = "log.file";
+pub const FILE_MAX_KIB_KEY: &str = "log.file_max_kib";
+pub const FILE_KEEP_KEY: &str = "log.file_keep";
+
+/// Syslog-compatible severities (RFC 5424 section 6.2.1).
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Level {
+    Emergency = 0,
+    Alert = 1,
+    Critical = 2,
+    Error = 3,
+    Warning = 4,
+    Notice = 5,
+    Info = 6,
+    Debug = 7,
+}
+
+impl Level {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Level::Emergency => "emerg",
+            Level::Alert => "alert",
+            Level::Critical => "crit",
+            Level::Error => "err",
+            Level::Warning => "warn",
+            Level::Notice => "notice",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        let lower = text.trim().to_ascii_lowercase();
+        match lower.as_str() {
+            "emerg" | "emergency" | "0" => Some(Level::Emergency),
+            "alert" | "1" => Some(Level::Alert),
+            "crit" | "critical" | "2" => Some(Level::Critical),
+            "err" | "error" | "3" => Some(Level::Error),
+            "warn" | "warning" | "4" => Some(Level::Warning),
+            "notice" | "5" => Some(Level::Notice),
+            "info" | "6" => Some(Level::Info),
+            "debug" | "7" => Some(Level::Debug),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct KlogRecord {
+    pub seq: u64,
+    pub ticks: u64,
+    pub uptime_ms: u64,
+    pub level: Level,
+    pub text: String,
+}
+
+struct KlogRing {
+    records: VecDeque<KlogRecord>,
+    next_seq: u64,
+    dropped: u64,
+}
+
+static KLOG: SpinLock<KlogRing> = SpinLock::new(KlogRing {
+    records: VecDeque::new(),
+    next_seq: 1,
+    dropped: 0,
+});
+
+fn clip_line(text: &str) -> String {
+    let mut end = text.len().min(KLOG_MAX_LINE_BYTES);
+    while end > 0 && !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    String::from(text[..end].trim_end())
+}
+
+/// Text buffer of fixed capacity for `write!`. Output that does not fit is
+/// dropped at a char boundary; it never allocates.
+#[derive(Clone, Copy)]
+pub struct FixedBuf<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+    truncated: bool,
+}
+
+impl<const N: usize> FixedBuf<N> {
+    pub const fn new() -> Self {
+        Self {
+            bytes: [0; N],
+            len: 0,
+            truncated: false,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only whole chars are ever copied in.
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or("")
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.truncated = false;
+    }
+}
+
+impl<const N: usize> Default for FixedBuf<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> fmt::Write for FixedBuf<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let room = N - self.len;
+        let mut take = s.len().min(room);
+        while take > 0 && !s.is_char_boundary(take) {
+            take -= 1;
+        }
+        self.bytes[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
+        if take < s.len() {
+            self.truncated = true;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+struct IrqRecord {
+    level: Level,
+    ticks: u64,
+    uptime_ms: u64,
+    text: FixedBuf<KLOG_MAX_LINE_BYTES>,
+}
+
+impl IrqRecord {
+    const EMPTY: Self = Self {
+        level: Level::Info,
+        ticks: 0,
+        uptime_ms: 0,
+        text: FixedBuf::new(),
+    };
+}
+
+struct IrqSlot {
+    record: UnsafeCell<IrqRecord>,
+    /// Position + 1 of the record last written here; the consumer reads the
+    /// slot only once this matches the position it expects.
+    ready: AtomicUsize,
+}
+
+/// Multi-producer, single-consumer ring. Producers on any core claim a
+/// position by moving `head` with a compare-exchange, fill the slot with
+/// interrupts masked, then publish it through `ready`. `DRAINING` keeps to
+/// one consumer at a time.
+struct IrqRing {
+    slots: [IrqSlot; KLOG_IRQ_SLOTS],
+    /// Next position to claim.
+    head: AtomicUsize,
+    /// Next position to read; only the consumer advances it.
+    tail: AtomicUsize,
+    dropped: AtomicU64,
+}
+
+// SAFETY: a position is claimed by exactly one producer, and only while it is
+// less than a ring's length ahead of `tail`; the consumer reads it only after
+// `ready` publishes it and frees it by moving `tail` past it.
+unsafe impl Sync for IrqRing {}
+
+static IRQ_RING: IrqRing = IrqRing {
+    slots: [const {
+        IrqSlot {
+            record: UnsafeCell::new(IrqRecord::EMPTY),
+            ready: AtomicUsize::new(0),
+        }
+    }; KLOG_IRQ_SLOTS],
+    head: AtomicUsize::new(0),
+    tail: AtomicUsize::new(0),
+    dropped: AtomicU64::new(0),
+};
+static DRAINING: AtomicBool = AtomicBool::new(false);
+
+impl IrqRing {
+    fn push(&self, level: Level, args: fmt::Arguments<'_>) -> bool {
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            if head.wrapping_sub(self.tail.load(Ordering::Acquire)) >= KLOG_IRQ_SLOTS {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                return false;
+            }
+            match self
+                .head
+                .compare_exchange_weak(head, head.wrapping_add(1), Ordering::AcqRel, Ordering::Relaxed)
+            {
+                Ok(_) => break,
+                Err(current) => head = current,
+            }
+        }
+        let snap = crate::timer::snapshot();
+        let slot = &self.slots[head % KLOG_IRQ_SLOTS];
+        let record = unsafe { &mut *slot.record.get() };
+        record.level = level;
+        record.ticks = snap.ticks;
+        record.uptime_ms = snap.uptime_ms;
+        record.text.clear();
+        let _ = fmt::write(&mut record.text, args);
+        slot.ready.store(head.wrapping_add(1), Ordering::Release);
+        true
+    }
+
+    /// The record at `tail`, once its producer has published it. A claimed
+    /// but unfinished slot holds back the records after it until the next
+    /// drain.
+    fn pop(&self) -> Option<IrqRecord> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let slot = &self.slots[tail % KLOG_IRQ_SLOTS];
+        if slot.ready.load(Ordering::Acquire) != tail.wrapping_add(1) {
+            return None;
+        }
+        let record = unsafe { *slot.record.get() };
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Some(record)
+    }
+}
+
+/// Queue a record without allocating or taking a lock. Safe in IRQ and
+/// exception handlers on any core; use through `klog_irq!`.
+/// Returns false when the IRQ ring is full and the record was dropped.
+pub fn log_irq(level: Level, args: fmt::Arguments<'_>) -> bool {
+    let rflags = crate::spinlock::save_and_disable_interrupts();
+    let queued = IRQ_RING.push(level, args);
+    crate::spinlock::restore_interrupts(rflags);
+    queued
+}
+
+/// `klog_irq!(Level::Warning, "nvme: cola {} atascada", qid)`: format and
+/// queue a record from interrupt context.
+macro_rules! klog_irq {
+    ($level:expr, $($arg:tt)*) => {
+        $crate::klog::log_irq($level, format_args!($($arg)*))
+    };
+}
+pub(crate) use klog_irq;
+
+/// Move queued IRQ records into the main ring, in order. Returns how many.
+pub fn drain_irq_records() -> usize {
+    if crate::allocator::heap_size_bytes() == 0 || DRAINING.swap(true, Ordering::Acquire) {
+        return 0;
+    }
+    let mut moved = 0usize;
+    while let Some(record) = IRQ_RING.pop() {
+        push_record(record.level, record.ticks, record.uptime_ms, record.text.as_str());
+        moved += 1;
+    }
+    DRAINING.store(false, Ordering::Release);
+    moved
+}
+
+/// Start the task that drains the IRQ ring; called once the heap is up.
+/// It polls on a timer rather than being woken, so producers never touch a
+/// lock.
+pub fn start_irq_drain() {
+    let drain = async {
+        loop {
+            drain_irq_records();
+            crate::executor::sleep_ms(KLOG_IRQ_DRAIN_MS).await;
+        }
+    };
+    if crate::executor::spawn("klog-irq", drain).is_none() {
+        panic!("no executor slot for the klog IRQ drain task");
+    }
+}
+
+pub fn irq_dropped_records() -> u64 {
+    IRQ_RING.dropped.load(Ordering::Relaxed)
+}
+
+fn push_record(level: Level, ticks: u64, uptime_ms: u64, text: &str) {
+    let line = clip_line(text);
+    let mut ring = KLOG.lock();
+    if ring.records.len() >= KLOG_MAX_RECORDS {
+        let _ = ring.records.pop_front();
+        ring.dropped = ring.dropped.saturating_add(1);
+    }
+    let seq = ring.next_seq;
+    ring.next_seq = ring.next_seq.saturating_add(1);
+    ring.records.push_back(KlogRecord {
+        seq,
+        ticks,
+        uptime_ms,
+        level,
+        text: line,
+    });
+}
+
+/// Record a line with an explicit severity.
+pub fn log(level: Level, text: &str) {
+    // The heap is not available until allocator::init_heap runs.
+    if crate::allocator::heap_size_bytes() == 0 || text.is_empty() {
+        return;
+    }
+    // Keep IRQ records that were already queued ahead of this one.
+    drain_irq_records();
+    let snap = crate::timer::snapshot();
+    push_record(level, snap.ticks, snap.uptime_ms, text);
+}
+
+/// Record a console line; severity is inferred from common error wording.
+pub fn record_console_line(text: &str) {
+    log(infer_level(text), text);
+}
+
+/// Most verbose severity `println` still puts on the console (`loglevel=`
+/// boot option); the ring keeps every line regardless.
+static CONSOLE_LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+
+pub fn set_console_level(level: Level) {
+    CONSOLE_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn shown_on_console(text: &str) -> bool {
+    infer_level(text) as u8 <= CONSOLE_LEVEL.load(Ordering::Relaxed)
+}
+
+fn infer_level(text: &str) -> Level {
+    let lower = text.to_ascii_lowercase();
+    if lower.contains("panic") {
+        Level::Critical
+    } else if lower.contains("error") || lower.contains("failed") || lower.contains("fallo") {
+        Level::Error
+    } else if lower.contains("warn") || lower.contains("timeout") {
+        Level::Warning
+    } else {
+        Level::Info
+    }
+}
+
+/// Sequence number that the next record will receive.
+pub fn next_seq() -> u64 {
+    KLOG.lock().next_seq
+}
+
+/// Sequence number of the oldest record still held in the ring.
+pub fn oldest_seq() -> u64 {
+    let ring = KLOG.lock();
+    ring.records.front().map(|r| r.seq).unwrap_or(ring.next_seq)
+}
+
+pub fn dropped_records() -> u64 {
+    KLOG.lock().dropped
+}
+
+pub fn record_count() -> usize {
+    KLOG.lock().records.len()
+}
+
+/// Copy up to `max` records whose sequence number is `>= from_seq`.
+pub fn records_since(from_seq: u64, max: usize) -> Vec<KlogRecord> {
+    drain_irq_records();
+    let ring = KLOG.lock();
+    ring.records
+        .iter()
+        .filter(|r| r.seq >= from_seq)
+        .take(max)
+        .cloned()
+        .collect()
+}
+
+/// Copy the newest `count` records, oldest first.
+pub fn tail(count: usize) -> Vec<KlogRecord> {
+    drain_irq_records();
+    let ring = KLOG.lock();
+    let skip = ring.records.len().saturating_sub(count);
+    ring.records.iter().skip(skip).cloned().collect()
+}
+
+pub fn clear() {
+    let mut ring = KLOG.lock();
+    ring.records.clear();
+    ring.dropped = 0;
+}
+
+pub fn format_record(record: &KlogRecord) -> String {
+    alloc::format!(
+        "[{:>6}.{:03}] {:<6} {}",
+        record.uptime_ms / 1000,
+        record.uptime_ms % 1000,
+        record.level.as_str(),
+        record.text
+    )
+}
+
+/// Write the whole ring, gzip-compressed, to the first free
+/// `LOGS/KLOGnnnn.GZ`. Returns the file name, record count and size.
+pub fn archive() -> Result<(String, usize, usize), &'static str> {
+    let records = tail(KLOG_MAX_RECORDS);
+    let mut text = String::new();
+    for record in records.iter() {
+        text.push_str(format_record(record).as_str());
+        text.push('\n');
+    }
+
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let dir = fat.ensure_subdirectory(fat.root_cluster, KLOG_ARCHIVE_DIR)?;
+    let existing = fat.read_dir_entries(dir)?;
+    let name = (0..KLOG_ARCHIVE_MAX_FILES)
+        .map(|n| alloc::format!("KLOG{:04}.GZ", n))
+        .find(|name| !existing.iter().any(|e| e.valid && e.matches_name(name.as_str())))
+        .ok_or("LOGS lleno (borra archivos antiguos)")?;
+    let inner = alloc::format!("KLOG{}.TXT", &name[4..8]);
+    let mtime = (crate::timer::wall_clock_unix_millis() / 1000).max(0) as u32;
+    let packed = crate::compress::gzip_compress(text.as_bytes(), crate::compress::DEFAULT_LEVEL, Some(inner.as_str()), mtime);
+    fat.write_text_file_in_dir(dir, name.as_str(), packed.as_slice())?;
+    Ok((name, records.len(), packed.len()))
+}
+
+/// State of the on-disk log, owned by whoever set `FILE_FLUSHING`.
+struct FileLog {
+    /// First record not yet written.
+    next_seq: u64,
+    /// Current contents of `SYSTEM.LOG`; `None` until read back this boot.
+    text: Option<String>,
+    rotations: u32,
+    last_error: Option<&'static str>,
+}
+
+static FILE_LOG: SpinLock<FileLog> = SpinLock::new(FileLog {
+    next_seq: 0,
+    text: None,
+    rotations: 0,
+    last_error: None,
+});
+static FILE_FLUSHING: AtomicBool = AtomicBool::new(false);
+
+/// Start copying the ring to `\REDUXOS\LOGS\SYSTEM.LOG` every couple of
+/// seconds, so the lines before a hang are still there after a reset. A
+/// flush only writes while the boot volume is mounted, and not in recovery
+/// mode, whose volume may be someone else's.
+pub fn start_file_log() {
+    crate::timer::every_ms(KLOG_FILE_FLUSH_MS, on_file_timer, 0);
+}
+
+fn on_file_timer(_: usize) {
+    let _ = flush_file();
+}
+
+fn file_limits() -> (usize, usize) {
+    let max_kib = crate::config::get_int(FILE_MAX_KIB_KEY, 512).clamp(16, 8192) as usize;
+    let keep = crate::config::get_int(FILE_KEEP_KEY, 4).clamp(1, 9) as usize;
+    (max_kib * 1024, keep)
+}
+
+fn archive_name(n: usize) -> String {
+    alloc::format!("SYSTEM{}.GZ", n)
+}
+
+/// Renames that make room for a new `SYSTEM1.GZ`, oldest first; the
+/// archive past `keep` is deleted before them.
+fn rotation_plan(keep: usize) -> Vec<(String, String)> {
+    (1..keep).rev().map(|n| (archive_name(n), archive_name(n + 1))).collect()
+}
+
+/// Append `records` to `out`, noting the ones the ring dropped before they
+/// were written. Returns the sequence number to continue from.
+fn append_records(out: &mut String, records: &[KlogRecord], next_seq: u64) -> u64 {
+    let mut next = next_seq;
+    for record in records.iter() {
+        if record.seq > next {
+            out.push_str(alloc::format!("-- {} registros perdidos --\n", record.seq - next).as_str());
+        }
+        out.push_str(format_record(record).as_str());
+        out.push('\n');
+        next = record.seq + 1;
+    }
+    next
+}
+
+fn boot_banner() -> String {
+    let (date, minute) = crate::calendar::local_now();
+    alloc::format!(
+        "==== arranque {} {} ({}) ====\n",
+        date.to_text(),
+        crate::calendar::hhmm(minute),
+        crate::bench::revision()
+    )
+}
+
+fn file_dir(fat: &mut crate::fat32::Fat32) -> Result<u32, &'static str> {
+    let mut dir = fat.root_cluster;
+    for name in KLOG_FILE_DIRS.iter() {
+        dir = fat.ensure_subdirectory(dir, name)?;
+    }
+    Ok(dir)
+}
+
+/// Compress `text` into `SYSTEM1.GZ`, shifting the older archives up.
+fn rotate_file(fat: &mut crate::fat32::Fat32, dir: u32, text: &str, keep: usize) -> Result<(), &'static str> {
+    let existing = fat.read_dir_entries(dir)?;
+    let present = |name: &str| existing.iter().any(|e| e.valid && e.matches_name(name));
+    let oldest = archive_name(keep);
+    if present(oldest.as_str()) {
+        fat.delete_file_in_dir(dir, oldest.as_str())?;
+    }
+    for (from, to) in rotation_plan(keep).iter() {
+        if present(from.as_str()) {
+            fat.rename_entry_in_dir(dir, from.as_str(), to.as_str(), Some(false))?;
+        }
+    }
+    let mtime = (crate::timer::wall_clock_unix_millis() / 1000).max(0) as u32;
+    let packed = crate::compress::gzip_compress(
+        text.as_bytes(),
+        crate::compress::DEFAULT_LEVEL,
+        Some(KLOG_FILE_NAME),
+        mtime,
+    );
+    fat.write_text_file_in_dir(dir, archive_name(1).as_str(), packed.as_slice())
+}
+
+/// Write the records logged since the last flush to `SYSTEM.LOG`, rotating
+/// it to a gzip archive once it passes `log.file_max_kib`. Returns how many
+/// records were written.
+pub fn flush_file() -> Result<usize, &'static str> {
+    if !crate::config::get_bool(FILE_KEY, true) || crate::recovery::active() {
+        return Ok(0);
+    }
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    if fat.init_status != crate::fat32::InitStatus::Success {
+        return Err("volumen de arranque no montado");
+    }
+    // Writing logs lines of its own; those wait for the next flush.
+    if FILE_FLUSHING.swap(true, Ordering::Acquire) {
+        return Ok(0);
+    }
+    let (next_seq, text) = {
+        let mut state = FILE_LOG.lock();
+        (state.next_seq, state.text.take())
+    };
+    let result = write_file(fat, next_seq, text);
+    let mut state = FILE_LOG.lock();
+    let written = match result {
+        Ok((next, text, rotated, written)) => {
+            state.next_seq = next;
+            state.text = Some(text);
+            state.rotations += rotated as u32;
+            state.last_error = None;
+            Ok(written)
+        }
+        Err(err) => {
+            state.last_error = Some(err);
+            Err(err)
+        }
+    };
+    drop(state);
+    FILE_FLUSHING.store(false, Ordering::Release);
+    written
+}
+
+/// The body of [`flush_file`]: new contents, next sequence number, whether
+/// the file was rotated and how many records went in.
+fn write_file(
+    fat: &mut crate::fat32::Fat32,
+    next_seq: u64,
+    text: Option<String>,
+) -> Result<(u64, String, bool, usize), &'static str> {
+    let records = records_since(next_seq, KLOG_MAX_RECORDS);
+    let dir = file_dir(fat)?;
+    let (max_bytes, keep) = file_limits();
+    let mut rotated = false;
+    let mut text = match text {
+        Some(text) if records.is_empty() => return Ok((next_seq, text, false, 0)),
+        Some(text) => text,
+        None => {
+            let old = fat
+                .read_file_in_dir(dir, KLOG_FILE_NAME)
+                .map(|raw| String::from_utf8_lossy(raw.as_slice()).into_owned())
+                .unwrap_or_default();
+            let mut text = if old.len() >= max_bytes {
+                rotate_file(fat, dir, old.as_str(), keep)?;
+                rotated = true;
+                String::new()
+            } else {
+                old
+            };
+            text.push_str(boot_banner().as_str());
+            text
+        }
+    };
+    let next = append_records(&mut text, records.as_slice(), next_seq);
+    if text.len() >= max_bytes {
+        rotate_file(fat, dir, text.as_str(), keep)?;
+        rotated = true;
+        text.clear();
+    }
+    fat.write_text_file_in_dir(dir, KLOG_FILE_NAME, text.as_bytes())?;
+    Ok((next, text, rotated, records.len()))
+}
+
+fn file_lines() -> Vec<String> {
+    let (max_bytes, keep) = file_limits();
+    let mut out = Vec::new();
+    if !crate::config::get_bool(FILE_KEY, true) {
+        out.push(alloc::format!("Log: archivo desactivado (config set {} true).", FILE_KEY));
+        return out;
+    }
+    let flushed = flush_file();
+    let state = FILE_LOG.lock();
+    out.push(alloc::format!(
+        "Log: \\REDUXOS\\LOGS\\{} ({} bytes de {} KiB, {} rotaciones; se guardan {} .GZ)",
+        KLOG_FILE_NAME,
+        state.text.as_ref().map(|t| t.len()).unwrap_or(0),
+        max_bytes / 1024,
+        state.rotations,
+        keep
+    ));
+    match flushed {
+        Ok(written) => out.push(alloc::format!("Log: {} registros nuevos escritos.", written)),
+        Err(err) => out.push(alloc::format!("Log: no se pudo escribir: {}", err)),
+    }
+    out
+}
+
+/// Shared implementation of the `log` / `dmesg` shell commands.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut parts = args.split_whitespace();
+    let sub = parts.next().unwrap_or("").to_ascii_lowercase();
+
+    if sub.is_empty() || sub == "show" || sub == "tail" {
+        let count = parts
+            .next()
+            .and_then(|n| n.parse::<usize>().ok())
+            .unwrap_or(40)
+            .clamp(1, KLOG_MAX_RECORDS);
+        let records = tail(count);
+        if records.is_empty() {
+            out.push(String::from("Log: (vacio)"));
+        }
+        for record in records.iter() {
+            out.push(format_record(record));
+        }
+        out.push(alloc::format!(
+            "Log: {} registros en memoria, {} descartados ({} desde IRQ).",
+            record_count(),
+            dropped_records(),
+            irq_dropped_records()
+        ));
+        return out;
+    }
+
+    if sub == "clear" {
+        clear();
+        out.push(String::from("Log: buffer limpiado."));
+        return out;
+    }
+
+    if sub == "save" {
+        match archive() {
+            Ok((name, lines, bytes)) => out.push(alloc::format!(
+                "Log: {} registros guardados en {}/{} ({} bytes gzip).",
+                lines, KLOG_ARCHIVE_DIR, name, bytes
+            )),
+            Err(e) => out.push(alloc::format!("Log: no se pudo guardar: {}", e)),
+        }
+        return out;
+    }
+
+    if sub == "file" {
+        return file_lines();
+    }
+
+    if sub == "remote" {
+        let rest: Vec<&str> = parts.collect();
+        out.extend(crate::net::syslog::command_lines(rest.as_slice()));
+        return out;
+    }
+
+    out.push(String::from("Uso: log [tail <n>] | log clear | log save | log file | log remote <ip[:puerto]> [udp|tcp] | log remote off | log remote status"));
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "klog";
+
+    fn fixed_buf_truncates_on_char_boundary() {
+        use core::fmt::Write;
+
+        let mut buf: FixedBuf<8> = FixedBuf::new();
+        let _ = write!(buf, "{}-{}", 12, "ab");
+        crate::selftest::ensure_eq(buf.as_str(), "12-ab", "cabe")?;
+        crate::selftest::ensure(!buf.is_truncated(), "sin recorte")?;
+        let _ = write!(buf, "ñañ");
+        crate::selftest::ensure_eq(buf.as_str(), "12-abña", "recorte en caracter")?;
+        crate::selftest::ensure(buf.is_truncated(), "recortado")
+    }
+
+    fn irq_records_reach_the_ring() {
+        let seq = next_seq();
+        crate::selftest::ensure(klog_irq!(Level::Notice, "selftest irq {}", 42), "encolado")?;
+        let records = records_since(seq, 16);
+        let record = records
+            .iter()
+            .find(|r| r.text == "selftest irq 42")
+            .ok_or_else(|| String::from("registro ausente"))?;
+        crate::selftest::ensure(record.level == Level::Notice, "nivel")
+    }
+
+    fn file_rotation_shifts_archives_up() {
+        let plan = rotation_plan(3);
+        crate::selftest::ensure_eq(plan.len(), 2, "renombrados")?;
+        crate::selftest::ensure_eq(plan[0].0.as_str(), "SYSTEM2.GZ", "primero el mas viejo")?;
+        crate::selftest::ensure_eq(plan[0].1.as_str(), "SYSTEM3.GZ", "destino")?;
+        crate::selftest::ensure_eq(plan[1].1.as_str(), "SYSTEM2.GZ", "luego el nuevo")?;
+        crate::selftest::ensure(rotation_plan(1).is_empty(), "uno solo")
+    }
+
+    fn file_notes_records_lost_before_a_flush() {
+        let record = |seq: u64| KlogRecord {
+            seq,
+            ticks: 0,
+            uptime_ms: 1_500,
+            level: Level::Info,
+            text: alloc::format!("linea {}", seq),
+        };
+        let mut text = String::new();
+        let next = append_records(&mut text, &[record(3), record(7)], 3);
+        crate::selftest::ensure_eq(next, 8, "siguiente")?;
+        let lines: Vec<&str> = text.lines().collect();
+        crate::selftest::ensure_eq(lines.len(), 3, "lineas")?;
+        crate::selftest::ensure(lines[0].ends_with("linea 3"), "primera")?;
+        crate::selftest::ensure_eq(lines[1], "-- 3 registros perdidos --", "hueco")
+    }
+}
@@ -0,0 +1,1214 @@
+//! Loadable kernel modules (`.rko`).
+//!
+//! A module is an x86_64 ELF relocatable object (`gcc -c -ffreestanding
+//! -fPIE -mno-red-zone -mgeneral-regs-only`, or rustc `--emit=obj` for
+//! x86_64-unknown-none). The loader places its `SHF_ALLOC` sections in a
+//! `wx::PageBuffer`, code first, applies the RELA relocations (absolute,
+//! PC-relative and GOT ones, with a GOT at the end of the image), makes the
+//! code pages read-only and executable, and calls
+//! `int rko_init(const struct rko_kernel_api *api)`. A module must not
+//! reference kernel symbols directly: everything it needs from the kernel is
+//! in `RkoKernelApi`, whose layout only grows at the end and whose
+//! `abi_version` changes on incompatible changes. An optional `.modinfo`
+//! section holds `key=value` strings; `name=` names the module and `abi=`
+//! must match `ABI_VERSION` when present.
+//!
+//! `mod load` reads the file through the VFS, along with a REDUX-SIG-V1
+//! `<file>.sig` when there is one. With Secure Boot on, a module without a
+//! valid signature is refused. Every module is measured into
+//! `tpm::PCR_MODULES` before it runs, and `rko_init` runs under
+//! `driver_guard`, so a fault in it fails the load instead of the kernel.
+//!
+//! Drivers register devices from `rko_init` with the `register_*` calls of
+//! the table. Input devices feed evdev events to the same queues as
+//! virtio-input; block and net devices are reachable through `block_read`,
+//! `block_write`, `net_send` and `net_receive`. `mod unload` calls
+//! `void rko_exit(void)` when the module has one, drops its devices and
+//! frees the image.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::spinlock::SpinLock;
+
+pub const ABI_VERSION: u32 = 1;
+/// Where `mod load <name>` looks for bare file names.
+pub const MODULE_DIR: &str = "REDUXOS/MODULES";
+const MAX_MODULE_BYTES: usize = 16 * 1024 * 1024;
+const PAGE: usize = 4096;
+const INPUT_EVENTS_PER_POLL: usize = 64;
+
+const ET_REL: u16 = 1;
+const EM_X86_64: u16 = 62;
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_RELA: u32 = 4;
+const SHT_NOBITS: u32 = 8;
+const SHF_WRITE: u64 = 0x1;
+const SHF_ALLOC: u64 = 0x2;
+const SHF_EXECINSTR: u64 = 0x4;
+const SHN_UNDEF: u16 = 0;
+const SHN_ABS: u16 = 0xFFF1;
+const SHN_COMMON: u16 = 0xFFF2;
+
+const R_X86_64_NONE: u32 = 0;
+const R_X86_64_64: u32 = 1;
+const R_X86_64_PC32: u32 = 2;
+const R_X86_64_PLT32: u32 = 4;
+const R_X86_64_GOTPCREL: u32 = 9;
+const R_X86_64_32: u32 = 10;
+const R_X86_64_32S: u32 = 11;
+const R_X86_64_PC64: u32 = 24;
+const R_X86_64_GOTPCRELX: u32 = 41;
+const R_X86_64_REX_GOTPCRELX: u32 = 42;
+
+const EV_SYN: u16 = 0;
+const EV_KEY: u16 = 1;
+const EV_REL: u16 = 2;
+const REL_X: u16 = 0;
+const REL_Y: u16 = 1;
+const REL_WHEEL: u16 = 8;
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+
+/// Block device a module registers. `read`/`write` move `count` blocks and
+/// return 0 on success.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RkoBlockOps {
+    pub name: *const u8,
+    pub name_len: usize,
+    pub ctx: *mut c_void,
+    pub block_size: u32,
+    pub block_count: u64,
+    pub read: extern "C" fn(ctx: *mut c_void, lba: u64, count: u32, buf: *mut u8) -> i32,
+    /// Null for read-only devices.
+    pub write: Option<extern "C" fn(ctx: *mut c_void, lba: u64, count: u32, buf: *const u8) -> i32>,
+}
+
+/// Ethernet device. `recv` returns the frame length, 0 when idle or a
+/// negative error.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RkoNetOps {
+    pub name: *const u8,
+    pub name_len: usize,
+    pub ctx: *mut c_void,
+    pub mac: [u8; 6],
+    pub mtu: u16,
+    pub send: extern "C" fn(ctx: *mut c_void, frame: *const u8, len: usize) -> i32,
+    pub recv: extern "C" fn(ctx: *mut c_void, buf: *mut u8, cap: usize) -> isize,
+}
+
+/// Linux evdev event (`type`, `code`, `value`).
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct RkoInputEvent {
+    pub kind: u16,
+    pub code: u16,
+    pub value: i32,
+}
+
+/// Input device. `poll` fills one event and returns 1, or returns 0 when
+/// the device has nothing queued.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RkoInputOps {
+    pub name: *const u8,
+    pub name_len: usize,
+    pub ctx: *mut c_void,
+    pub poll: extern "C" fn(ctx: *mut c_void, event: *mut RkoInputEvent) -> i32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct RkoPciInfo {
+    pub bus: u8,
+    pub slot: u8,
+    pub func: u8,
+    pub class_code: u8,
+    pub sub_class: u8,
+    pub prog_if: u8,
+    pub vendor_id: u16,
+    pub device_id: u16,
+}
+
+/// The kernel side of the module ABI, passed to `rko_init`.
+#[repr(C)]
+pub struct RkoKernelApi {
+    pub abi_version: u32,
+    pub size: u32,
+    pub log: extern "C" fn(text: *const u8, len: usize),
+    pub alloc: extern "C" fn(size: usize, align: usize) -> *mut u8,
+    pub free: extern "C" fn(ptr: *mut u8, size: usize, align: usize),
+    /// Timer ticks (10 ms) since boot.
+    pub ticks: extern "C" fn() -> u64,
+    pub inb: extern "C" fn(port: u16) -> u8,
+    pub outb: extern "C" fn(port: u16, value: u8),
+    pub inl: extern "C" fn(port: u16) -> u32,
+    pub outl: extern "C" fn(port: u16, value: u32),
+    /// Fills `out` with the `index`-th enumerated PCI function; 0 on success.
+    pub pci_device: extern "C" fn(index: usize, out: *mut RkoPciInfo) -> i32,
+    pub pci_read_config: extern "C" fn(bus: u8, slot: u8, func: u8, offset: u8) -> u32,
+    pub pci_write_config: extern "C" fn(bus: u8, slot: u8, func: u8, offset: u8, value: u32),
+    /// BAR address, 0 if unset. Also enables memory space and bus mastering.
+    pub pci_bar: extern "C" fn(bus: u8, slot: u8, func: u8, index: u8) -> u64,
+    /// These return a device index, or -1 outside `rko_init`.
+    pub register_block: extern "C" fn(ops: *const RkoBlockOps) -> i32,
+    pub register_net: extern "C" fn(ops: *const RkoNetOps) -> i32,
+    pub register_input: extern "C" fn(ops: *const RkoInputOps) -> i32,
+}
+
+#[derive(Clone, Copy)]
+enum DeviceOps {
+    Block(RkoBlockOps),
+    Net(RkoNetOps),
+    Input(RkoInputOps),
+}
+
+impl DeviceOps {
+    fn class(&self) -> &'static str {
+        match self {
+            DeviceOps::Block(_) => "block",
+            DeviceOps::Net(_) => "net",
+            DeviceOps::Input(_) => "input",
+        }
+    }
+
+    fn prefix(&self) -> &'static str {
+        match self {
+            DeviceOps::Block(_) => "blk",
+            DeviceOps::Net(_) => "eth",
+            DeviceOps::Input(_) => "input",
+        }
+    }
+}
+
+/// Pointer state an input device builds up until EV_SYN.
+#[derive(Clone, Copy, Default)]
+struct PointerState {
+    dx: i32,
+    dy: i32,
+    wheel: i32,
+    left: bool,
+    right: bool,
+    dirty: bool,
+}
+
+struct Device {
+    module: u32,
+    name: String,
+    ops: DeviceOps,
+    pointer: PointerState,
+}
+
+// The context pointers belong to module code, which only runs from the
+// kernel thread that polls the devices.
+unsafe impl Send for Device {}
+
+struct Module {
+    id: u32,
+    name: String,
+    path: String,
+    image: crate::wx::PageBuffer,
+    code_len: usize,
+    exit: Option<usize>,
+    /// `modules/<name>` in the device tree.
+    node: crate::device::DeviceId,
+}
+
+static MODULES: SpinLock<Vec<Module>> = SpinLock::new(Vec::new());
+static DEVICES: SpinLock<Vec<Device>> = SpinLock::new(Vec::new());
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+/// Id of the module whose `rko_init` is running, 0 otherwise.
+static LOADING: AtomicU32 = AtomicU32::new(0);
+static LOADING_NODE: SpinLock<Option<crate::device::DeviceId>> = SpinLock::new(None);
+
+fn read_u16(raw: &[u8], off: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(raw.get(off..off + 2)?.try_into().ok()?))
+}
+
+fn read_u32(raw: &[u8], off: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(raw.get(off..off + 4)?.try_into().ok()?))
+}
+
+fn read_u64(raw: &[u8], off: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(raw.get(off..off + 8)?.try_into().ok()?))
+}
+
+fn c_str(raw: &[u8], off: usize) -> &str {
+    let tail = raw.get(off..).unwrap_or(&[]);
+    let end = tail.iter().position(|&b| b == 0).unwrap_or(tail.len());
+    core::str::from_utf8(&tail[..end]).unwrap_or("")
+}
+
+fn align_up(value: usize, align: usize) -> Option<usize> {
+    value.checked_next_multiple_of(align.max(1))
+}
+
+#[derive(Clone, Copy)]
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u64,
+    offset: usize,
+    size: usize,
+    link: u32,
+    info: u32,
+    align: usize,
+}
+
+impl SectionHeader {
+    fn read(raw: &[u8], at: usize) -> Option<Self> {
+        Some(Self {
+            name: read_u32(raw, at)?,
+            kind: read_u32(raw, at + 4)?,
+            flags: read_u64(raw, at + 8)?,
+            offset: read_u64(raw, at + 0x18)? as usize,
+            size: read_u64(raw, at + 0x20)? as usize,
+            link: read_u32(raw, at + 0x28)?,
+            info: read_u32(raw, at + 0x2C)?,
+            align: read_u64(raw, at + 0x30)? as usize,
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Symbol {
+    name: u32,
+    shndx: u16,
+    value: u64,
+}
+
+/// An `.rko` file checked and laid out, before it is placed in memory.
+struct Object<'a> {
+    raw: &'a [u8],
+    sections: Vec<SectionHeader>,
+    /// Image offset of each `SHF_ALLOC` section.
+    placement: Vec<Option<usize>>,
+    code_len: usize,
+    got_offset: usize,
+    got_slots: usize,
+    image_len: usize,
+    symbols: Vec<Symbol>,
+    strtab: usize,
+    shstrtab: usize,
+}
+
+impl<'a> Object<'a> {
+    fn parse(raw: &'a [u8]) -> Result<Self, &'static str> {
+        if raw.get(..4) != Some(b"\x7fELF".as_slice()) || raw.get(4) != Some(&2) || raw.get(5) != Some(&1) {
+            return Err("no es un ELF64 little-endian");
+        }
+        if read_u16(raw, 0x10) != Some(ET_REL) || read_u16(raw, 0x12) != Some(EM_X86_64) {
+            return Err("no es un objeto reubicable x86_64 (ET_REL)");
+        }
+        let shoff = read_u64(raw, 0x28).ok_or("cabecera ELF truncada")? as usize;
+        let shentsize = read_u16(raw, 0x3A).ok_or("cabecera ELF truncada")? as usize;
+        let shnum = read_u16(raw, 0x3C).ok_or("cabecera ELF truncada")? as usize;
+        let shstrndx = read_u16(raw, 0x3E).ok_or("cabecera ELF truncada")? as usize;
+        if shentsize < 64 || shnum == 0 {
+            return Err("tabla de secciones invalida");
+        }
+        let mut sections = Vec::with_capacity(shnum);
+        for index in 0..shnum {
+            let at = shoff
+                .checked_add(index * shentsize)
+                .ok_or("tabla de secciones invalida")?;
+            let header = SectionHeader::read(raw, at).ok_or("tabla de secciones truncada")?;
+            if header.kind != SHT_NOBITS && header.offset.checked_add(header.size).is_none_or(|end| end > raw.len()) {
+                return Err("seccion fuera del archivo");
+            }
+            sections.push(header);
+        }
+        let shstrtab = sections.get(shstrndx).map(|s| s.offset).unwrap_or(0);
+
+        // Code first so one page-aligned prefix of the image can be executable.
+        let mut placement = alloc::vec![None; sections.len()];
+        let mut cursor = 0usize;
+        for pass_code in [true, false] {
+            for (index, section) in sections.iter().enumerate() {
+                if section.flags & SHF_ALLOC == 0 || section.size == 0 {
+                    continue;
+                }
+                if (section.flags & SHF_EXECINSTR != 0) != pass_code {
+                    continue;
+                }
+                if pass_code && section.flags & SHF_WRITE != 0 {
+                    return Err("seccion de codigo escribible (W^X)");
+                }
+                cursor = align_up(cursor, section.align).ok_or("seccion fuera de rango")?;
+                placement[index] = Some(cursor);
+                cursor = cursor.checked_add(section.size).ok_or("seccion fuera de rango")?;
+            }
+            if pass_code {
+                cursor = align_up(cursor, PAGE).ok_or("seccion fuera de rango")?;
+            }
+        }
+        let code_len = sections
+            .iter()
+            .zip(placement.iter())
+            .filter(|(s, p)| p.is_some() && s.flags & SHF_EXECINSTR != 0)
+            .map(|(s, p)| p.unwrap_or(0) + s.size)
+            .max()
+            .unwrap_or(0);
+
+        let symtab = sections
+            .iter()
+            .find(|s| s.kind == SHT_SYMTAB)
+            .copied()
+            .ok_or("sin tabla de simbolos")?;
+        let strtab = sections
+            .get(symtab.link as usize)
+            .ok_or("tabla de simbolos sin nombres")?
+            .offset;
+        let mut symbols = Vec::with_capacity(symtab.size / 24);
+        for index in 0..symtab.size / 24 {
+            let at = symtab.offset + index * 24;
+            symbols.push(Symbol {
+                name: read_u32(raw, at).ok_or("simbolo truncado")?,
+                shndx: read_u16(raw, at + 6).ok_or("simbolo truncado")?,
+                value: read_u64(raw, at + 8).ok_or("simbolo truncado")?,
+            });
+        }
+
+        let mut got_slots = 0usize;
+        for section in sections.iter().filter(|s| s.kind == SHT_RELA) {
+            for index in 0..section.size / 24 {
+                let kind = read_u64(raw, section.offset + index * 24 + 8).unwrap_or(0) as u32;
+                if matches!(kind, R_X86_64_GOTPCREL | R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX) {
+                    got_slots += 1;
+                }
+            }
+        }
+        let got_offset = align_up(cursor, 8).ok_or("tamano de modulo invalido")?;
+        let image_len = got_slots
+            .checked_mul(8)
+            .and_then(|got| got_offset.checked_add(got))
+            .ok_or("tamano de modulo invalido")?;
+        if image_len == 0 || image_len > MAX_MODULE_BYTES {
+            return Err("tamano de modulo invalido");
+        }
+        Ok(Self {
+            raw,
+            sections,
+            placement,
+            code_len,
+            got_offset,
+            got_slots,
+            image_len,
+            symbols,
+            strtab,
+            shstrtab,
+        })
+    }
+
+    fn section_name(&self, section: &SectionHeader) -> &str {
+        c_str(self.raw, self.shstrtab + section.name as usize)
+    }
+
+    fn symbol_name(&self, symbol: &Symbol) -> &str {
+        c_str(self.raw, self.strtab + symbol.name as usize)
+    }
+
+    /// Runtime address of `symbol` with the image at `base`.
+    fn symbol_address(&self, symbol: &Symbol, base: u64) -> Result<u64, String> {
+        match symbol.shndx {
+            SHN_UNDEF if symbol.name == 0 => Ok(0),
+            SHN_UNDEF => Err(alloc::format!(
+                "simbolo externo '{}': los modulos solo usan la tabla RkoKernelApi",
+                self.symbol_name(symbol)
+            )),
+            SHN_ABS => Ok(symbol.value),
+            SHN_COMMON => Err(String::from("simbolos COMMON no soportados (compila con -fno-common)")),
+            index => match self.placement.get(index as usize).copied().flatten() {
+                Some(offset) => Ok(base + offset as u64 + symbol.value),
+                None => Err(alloc::format!(
+                    "simbolo '{}' en una seccion no cargada",
+                    self.symbol_name(symbol)
+                )),
+            },
+        }
+    }
+
+    fn find_symbol(&self, name: &str, base: u64) -> Option<u64> {
+        let symbol = self
+            .symbols
+            .iter()
+            .find(|s| s.shndx != SHN_UNDEF && s.name != 0 && self.symbol_name(s) == name)?;
+        self.symbol_address(symbol, base).ok()
+    }
+
+    fn modinfo(&self) -> Vec<(&str, &str)> {
+        let Some(section) = self.sections.iter().find(|s| self.section_name(s) == ".modinfo") else {
+            return Vec::new();
+        };
+        let bytes = self
+            .raw
+            .get(section.offset..section.offset + section.size)
+            .unwrap_or(&[]);
+        parse_modinfo(bytes)
+    }
+
+    /// Copy the sections into `image` (mapped at `base`) and relocate them.
+    fn place(&self, image: &mut [u8], base: u64) -> Result<(), String> {
+        for (section, placement) in self.sections.iter().zip(self.placement.iter()) {
+            let Some(offset) = *placement else {
+                continue;
+            };
+            if section.kind == SHT_NOBITS {
+                continue;
+            }
+            image[offset..offset + section.size]
+                .copy_from_slice(&self.raw[section.offset..section.offset + section.size]);
+        }
+        let mut got_next = 0usize;
+        for section in self.sections.iter().filter(|s| s.kind == SHT_RELA) {
+            let Some(target) = self.placement.get(section.info as usize).copied().flatten() else {
+                // Relocations for debug info and other sections that are not loaded.
+                continue;
+            };
+            for index in 0..section.size / 24 {
+                let at = section.offset + index * 24;
+                let (Some(offset), Some(info), Some(addend)) = (
+                    read_u64(self.raw, at),
+                    read_u64(self.raw, at + 8),
+                    read_u64(self.raw, at + 16),
+                ) else {
+                    return Err(String::from("reubicacion truncada"));
+                };
+                let symbol = self
+                    .symbols
+                    .get((info >> 32) as usize)
+                    .ok_or("reubicacion con simbolo invalido")?;
+                let value = self.symbol_address(symbol, base)?;
+                let kind = info as u32;
+                let got = if matches!(kind, R_X86_64_GOTPCREL | R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX) {
+                    let slot = self.got_offset + got_next * 8;
+                    got_next += 1;
+                    image[slot..slot + 8].copy_from_slice(&value.to_le_bytes());
+                    Some(base + slot as u64)
+                } else {
+                    None
+                };
+                let place = target
+                    .checked_add(offset as usize)
+                    .ok_or("reubicacion fuera de la imagen")?;
+                relocate(image, place, base + place as u64, kind, value, addend as i64, got)?;
+            }
+        }
+        debug_assert!(got_next <= self.got_slots);
+        Ok(())
+    }
+}
+
+/// `key=value` strings separated by NUL bytes.
+fn parse_modinfo(bytes: &[u8]) -> Vec<(&str, &str)> {
+    bytes
+        .split(|&b| b == 0)
+        .filter_map(|entry| core::str::from_utf8(entry).ok()?.split_once('='))
+        .collect()
+}
+
+/// Apply one x86_64 RELA relocation at `image[at..]`, whose runtime address
+/// is `place`. `got` is the address of the GOT slot holding `value` for the
+/// GOT-relative kinds.
+fn relocate(
+    image: &mut [u8],
+    at: usize,
+    place: u64,
+    kind: u32,
+    value: u64,
+    addend: i64,
+    got: Option<u64>,
+) -> Result<(), String> {
+    let target = value.wrapping_add(addend as u64);
+    let pc_relative = |to: u64| -> Result<[u8; 4], String> {
+        let delta = to.wrapping_sub(place) as i64;
+        i32::try_from(delta)
+            .map(|v| v.to_le_bytes())
+            .map_err(|_| String::from("salto relativo fuera de rango (+-2 GiB)"))
+    };
+    let bytes: Vec<u8> = match kind {
+        R_X86_64_NONE => return Ok(()),
+        R_X86_64_64 => target.to_le_bytes().to_vec(),
+        R_X86_64_PC64 => target.wrapping_sub(place).to_le_bytes().to_vec(),
+        R_X86_64_PC32 | R_X86_64_PLT32 => pc_relative(target)?.to_vec(),
+        R_X86_64_GOTPCREL | R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX => {
+            let slot = got.ok_or("reubicacion GOT sin entrada")?;
+            pc_relative(slot.wrapping_add(addend as u64))?.to_vec()
+        }
+        R_X86_64_32 => u32::try_from(target)
+            .map_err(|_| String::from("direccion absoluta de 32 bits fuera de rango (compila con -fPIE)"))?
+            .to_le_bytes()
+            .to_vec(),
+        R_X86_64_32S => i32::try_from(target as i64)
+            .map_err(|_| String::from("direccion absoluta de 32 bits fuera de rango (compila con -fPIE)"))?
+            .to_le_bytes()
+            .to_vec(),
+        other => return Err(alloc::format!("tipo de reubicacion {} no soportado", other)),
+    };
+    image
+        .get_mut(at..at + bytes.len())
+        .ok_or_else(|| String::from("reubicacion fuera de la seccion"))?
+        .copy_from_slice(bytes.as_slice());
+    Ok(())
+}
+
+extern "C" fn api_log(text: *const u8, len: usize) {
+    if text.is_null() {
+        return;
+    }
+    let bytes = unsafe { core::slice::from_raw_parts(text, len) };
+    crate::klog::log(
+        crate::klog::Level::Info,
+        alloc::format!("mod: {}", String::from_utf8_lossy(bytes)).as_str(),
+    );
+}
+
+extern "C" fn api_alloc(size: usize, align: usize) -> *mut u8 {
+    match core::alloc::Layout::from_size_align(size.max(1), align.max(1)) {
+        Ok(layout) => unsafe { alloc::alloc::alloc_zeroed(layout) },
+        Err(_) => core::ptr::null_mut(),
+    }
+}
+
+extern "C" fn api_free(ptr: *mut u8, size: usize, align: usize) {
+    if ptr.is_null() {
+        return;
+    }
+    if let Ok(layout) = core::alloc::Layout::from_size_align(size.max(1), align.max(1)) {
+        unsafe { alloc::alloc::dealloc(ptr, layout) };
+    }
+}
+
+extern "C" fn api_ticks() -> u64 {
+    crate::timer::ticks()
+}
+
+extern "C" fn api_inb(port: u16) -> u8 {
+    unsafe { crate::hal::inb(port) }
+}
+
+extern "C" fn api_outb(port: u16, value: u8) {
+    unsafe { crate::hal::outb(port, value) }
+}
+
+extern "C" fn api_inl(port: u16) -> u32 {
+    unsafe { crate::hal::inl(port) }
+}
+
+extern "C" fn api_outl(port: u16, value: u32) {
+    unsafe { crate::hal::outl(port, value) }
+}
+
+extern "C" fn api_pci_device(index: usize, out: *mut RkoPciInfo) -> i32 {
+    let Some(info) = crate::pci::devices().get(index).copied() else {
+        return -1;
+    };
+    if out.is_null() {
+        return -1;
+    }
+    unsafe {
+        *out = RkoPciInfo {
+            bus: info.device.bus,
+            slot: info.device.slot,
+            func: info.device.func,
+            class_code: info.class_code,
+            sub_class: info.sub_class,
+            prog_if: info.prog_if,
+            vendor_id: info.device.vendor_id,
+            device_id: info.device.device_id,
+        };
+    }
+    0
+}
+
+extern "C" fn api_pci_read_config(bus: u8, slot: u8, func: u8, offset: u8) -> u32 {
+    unsafe { crate::pci::read_config(bus, slot, func, offset) }
+}
+
+extern "C" fn api_pci_write_config(bus: u8, slot: u8, func: u8, offset: u8, value: u32) {
+    unsafe { crate::pci::write_config(bus, slot, func, offset, value) }
+}
+
+extern "C" fn api_pci_bar(bus: u8, slot: u8, func: u8, index: u8) -> u64 {
+    unsafe {
+        crate::pci::enable_memory_space(bus, slot, func);
+        crate::pci::enable_bus_master(bus, slot, func);
+        crate::pci::read_bar(bus, slot, func, index).unwrap_or(0)
+    }
+}
+
+fn register(name: *const u8, name_len: usize, ops: DeviceOps) -> i32 {
+    let module = LOADING.load(Ordering::Acquire);
+    if module == 0 {
+        return -1;
+    }
+    let name = if name.is_null() {
+        String::from(ops.class())
+    } else {
+        String::from_utf8_lossy(unsafe { core::slice::from_raw_parts(name, name_len.min(64)) }).into_owned()
+    };
+    if let Some(parent) = *LOADING_NODE.lock() {
+        let node = crate::device::add_class_device(Some(parent), ops.prefix(), ops.class(), "rko");
+        crate::device::set_attr(node, "name", name.as_str());
+    }
+    let mut devices = DEVICES.lock();
+    devices.push(Device {
+        module,
+        name,
+        ops,
+        pointer: PointerState::default(),
+    });
+    devices.len() as i32 - 1
+}
+
+extern "C" fn api_register_block(ops: *const RkoBlockOps) -> i32 {
+    if ops.is_null() {
+        return -1;
+    }
+    let ops = unsafe { *ops };
+    register(ops.name, ops.name_len, DeviceOps::Block(ops))
+}
+
+extern "C" fn api_register_net(ops: *const RkoNetOps) -> i32 {
+    if ops.is_null() {
+        return -1;
+    }
+    let ops = unsafe { *ops };
+    register(ops.name, ops.name_len, DeviceOps::Net(ops))
+}
+
+extern "C" fn api_register_input(ops: *const RkoInputOps) -> i32 {
+    if ops.is_null() {
+        return -1;
+    }
+    let ops = unsafe { *ops };
+    register(ops.name, ops.name_len, DeviceOps::Input(ops))
+}
+
+static API: RkoKernelApi = RkoKernelApi {
+    abi_version: ABI_VERSION,
+    size: core::mem::size_of::<RkoKernelApi>() as u32,
+    log: api_log,
+    alloc: api_alloc,
+    free: api_free,
+    ticks: api_ticks,
+    inb: api_inb,
+    outb: api_outb,
+    inl: api_inl,
+    outl: api_outl,
+    pci_device: api_pci_device,
+    pci_read_config: api_pci_read_config,
+    pci_write_config: api_pci_write_config,
+    pci_bar: api_pci_bar,
+    register_block: api_register_block,
+    register_net: api_register_net,
+    register_input: api_register_input,
+};
+
+/// Link `raw` and run its `rko_init`. `fallback_name` names the module when
+/// it has no `.modinfo` name. `signature` is the REDUX-SIG-V1 text that
+/// came with the file; it is checked whenever present and required with
+/// Secure Boot on. Returns the module name.
+pub fn load_image(fallback_name: &str, path: &str, raw: &[u8], signature: Option<&str>) -> Result<String, String> {
+    match signature {
+        Some(text) => crate::pkg::verify_signature(fallback_name, raw, text, None)?,
+        None if crate::secureboot::state().enabled => {
+            return Err(alloc::format!("Secure Boot activo: {} no esta firmado", path));
+        }
+        None => {}
+    }
+    let object = Object::parse(raw).map_err(String::from)?;
+    let info = object.modinfo();
+    let name = String::from(
+        info.iter()
+            .find(|(key, _)| *key == "name")
+            .map(|(_, value)| *value)
+            .unwrap_or(fallback_name),
+    );
+    if let Some((_, abi)) = info.iter().find(|(key, _)| *key == "abi") {
+        if abi.parse::<u32>().ok() != Some(ABI_VERSION) {
+            return Err(alloc::format!(
+                "'{}' es para la ABI {} y el kernel tiene la {}",
+                name,
+                abi,
+                ABI_VERSION
+            ));
+        }
+    }
+    if MODULES.lock().iter().any(|m| m.name == name) {
+        return Err(alloc::format!("'{}' ya esta cargado", name));
+    }
+
+    let mut image = crate::wx::PageBuffer::zeroed(object.image_len).ok_or("sin memoria para el modulo")?;
+    let base = image.as_ptr() as u64;
+    object.place(image.as_mut_slice(), base)?;
+    let init = object.find_symbol("rko_init", base).ok_or("falta rko_init")?;
+    let exit = object.find_symbol("rko_exit", base).map(|addr| addr as usize);
+    if object.code_len > 0 {
+        image.make_executable(0, object.code_len);
+    }
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let node = crate::device::add(
+        Some(crate::device::add(None, "modules", None, None)),
+        name.as_str(),
+        None,
+        Some("rko"),
+    );
+    crate::device::set_attr(node, "path", path);
+    crate::tpm::measure(crate::tpm::PCR_MODULES, path, raw);
+    // The guard keeps the name for the failure list; one leak per module name.
+    let guard_name: &'static str = alloc::boxed::Box::leak(name.clone().into_boxed_str());
+    LOADING.store(id, Ordering::Release);
+    *LOADING_NODE.lock() = Some(node);
+    let init: extern "C" fn(*const RkoKernelApi) -> i32 = unsafe { core::mem::transmute(init as usize) };
+    let status = crate::driver_guard::call(guard_name, || init(&API));
+    *LOADING_NODE.lock() = None;
+    LOADING.store(0, Ordering::Release);
+    if status != Some(0) {
+        DEVICES.lock().retain(|d| d.module != id);
+        crate::device::remove(node);
+        return Err(match status {
+            Some(status) => alloc::format!("rko_init de '{}' devolvio {}", name, status),
+            None => alloc::format!("rko_init de '{}' fallo y se aislo", name),
+        });
+    }
+    MODULES.lock().push(Module {
+        id,
+        name: name.clone(),
+        path: String::from(path),
+        image,
+        code_len: object.code_len,
+        exit,
+        node,
+    });
+    Ok(name)
+}
+
+fn read_module_file(path: &str) -> Result<(String, Vec<u8>), String> {
+    let full = if path.contains('/') || path.contains('\\') {
+        crate::fs::vfs::normalize(path)
+    } else {
+        crate::fs::vfs::join(MODULE_DIR, path)
+    };
+    let stat = crate::fs::vfs::stat(&full).map_err(|err| alloc::format!("{}: {}", full, err))?;
+    if stat.is_dir {
+        return Err(alloc::format!("{} es un directorio", full));
+    }
+    if stat.size > MAX_MODULE_BYTES as u64 {
+        return Err(String::from("modulo demasiado grande"));
+    }
+    let raw = crate::fs::vfs::read(&full).map_err(|err| alloc::format!("{}: {}", full, err))?;
+    Ok((full, raw))
+}
+
+/// Load a `.rko` through the VFS; bare names come from `MODULE_DIR`. A
+/// REDUX-SIG-V1 signature next to it (`<path>.sig`) is checked when present.
+pub fn load(path: &str) -> Result<String, String> {
+    let (full, raw) = read_module_file(path)?;
+    let signature = crate::fs::vfs::read(&alloc::format!("{}.sig", full))
+        .ok()
+        .map(|text| String::from_utf8_lossy(&text).into_owned());
+    let stem = full.rsplit('/').next().unwrap_or("").split('.').next().unwrap_or("");
+    load_image(&stem.to_ascii_lowercase(), &full, &raw, signature.as_deref())
+}
+
+/// Run `rko_exit`, drop the module's devices and free its image.
+pub fn unload(name: &str) -> Result<usize, String> {
+    let (id, exit, node) = {
+        let modules = MODULES.lock();
+        let module = modules
+            .iter()
+            .find(|m| m.name == name)
+            .ok_or_else(|| alloc::format!("'{}' no esta cargado", name))?;
+        (module.id, module.exit, module.node)
+    };
+    if let Some(exit) = exit {
+        let exit: extern "C" fn() = unsafe { core::mem::transmute(exit) };
+        exit();
+    }
+    let removed = {
+        let mut devices = DEVICES.lock();
+        let before = devices.len();
+        devices.retain(|d| d.module != id);
+        before - devices.len()
+    };
+    MODULES.lock().retain(|m| m.id != id);
+    crate::device::remove(node);
+    Ok(removed)
+}
+
+fn device_ops(index: usize) -> Option<DeviceOps> {
+    DEVICES.lock().get(index).map(|d| d.ops)
+}
+
+/// Read whole blocks from module block device `index` into `buf`.
+pub fn block_read(index: usize, lba: u64, buf: &mut [u8]) -> Result<(), &'static str> {
+    let Some(DeviceOps::Block(ops)) = device_ops(index) else {
+        return Err("no es un dispositivo de bloques");
+    };
+    let size = ops.block_size.max(1) as usize;
+    if buf.len() % size != 0 || lba.saturating_add((buf.len() / size) as u64) > ops.block_count {
+        return Err("lectura fuera del dispositivo");
+    }
+    match (ops.read)(ops.ctx, lba, (buf.len() / size) as u32, buf.as_mut_ptr()) {
+        0 => Ok(()),
+        _ => Err("error de lectura del driver"),
+    }
+}
+
+pub fn block_write(index: usize, lba: u64, buf: &[u8]) -> Result<(), &'static str> {
+    let Some(DeviceOps::Block(ops)) = device_ops(index) else {
+        return Err("no es un dispositivo de bloques");
+    };
+    let write = ops.write.ok_or("dispositivo de solo lectura")?;
+    let size = ops.block_size.max(1) as usize;
+    if buf.len() % size != 0 || lba.saturating_add((buf.len() / size) as u64) > ops.block_count {
+        return Err("escritura fuera del dispositivo");
+    }
+    match write(ops.ctx, lba, (buf.len() / size) as u32, buf.as_ptr()) {
+        0 => Ok(()),
+        _ => Err("error de escritura del driver"),
+    }
+}
+
+pub fn net_send(index: usize, frame: &[u8]) -> Result<(), &'static str> {
+    let Some(DeviceOps::Net(ops)) = device_ops(index) else {
+        return Err("no es un dispositivo de red");
+    };
+    match (ops.send)(ops.ctx, frame.as_ptr(), frame.len()) {
+        0 => Ok(()),
+        _ => Err("error de envio del driver"),
+    }
+}
+
+/// One received frame, or `None` when the device is idle.
+pub fn net_receive(index: usize, buf: &mut [u8]) -> Option<usize> {
+    let Some(DeviceOps::Net(ops)) = device_ops(index) else {
+        return None;
+    };
+    let len = (ops.recv)(ops.ctx, buf.as_mut_ptr(), buf.len());
+    (len > 0).then_some((len as usize).min(buf.len()))
+}
+
+/// Feed events from module input devices to the virtio-input queues.
+pub fn poll_input() {
+    let inputs: Vec<(usize, RkoInputOps)> = DEVICES
+        .lock()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, d)| match d.ops {
+            DeviceOps::Input(ops) => Some((index, ops)),
+            _ => None,
+        })
+        .collect();
+    if inputs.is_empty() {
+        return;
+    }
+    let mut events = Vec::new();
+    for (index, ops) in inputs.iter() {
+        for _ in 0..INPUT_EVENTS_PER_POLL {
+            let mut event = RkoInputEvent::default();
+            if (ops.poll)(ops.ctx, &mut event) != 1 {
+                break;
+            }
+            events.push((*index, event));
+        }
+    }
+    let mut devices = DEVICES.lock();
+    for (index, event) in events {
+        if let Some(device) = devices.get_mut(index) {
+            if let Some(report) = handle_input(&mut device.pointer, event) {
+                crate::input::push_virtio_pointer(report);
+            }
+        }
+    }
+}
+
+/// Same evdev handling as virtio-input, minus absolute axes.
+fn handle_input(state: &mut PointerState, event: RkoInputEvent) -> Option<crate::input::VirtioPointerReport> {
+    match (event.kind, event.code) {
+        (EV_REL, REL_X) => state.dx = state.dx.saturating_add(event.value),
+        (EV_REL, REL_Y) => state.dy = state.dy.saturating_add(event.value),
+        (EV_REL, REL_WHEEL) => state.wheel = state.wheel.saturating_add(event.value),
+        (EV_KEY, BTN_LEFT) => state.left = event.value != 0,
+        (EV_KEY, BTN_RIGHT) => state.right = event.value != 0,
+        (EV_KEY, code) => {
+            crate::input::push_virtio_key(code, event.value.max(0) as u32);
+            return None;
+        }
+        (EV_SYN, _) if state.dirty => {
+            let report = crate::input::VirtioPointerReport {
+                dx: state.dx,
+                dy: state.dy,
+                wheel: state.wheel,
+                left: state.left,
+                right: state.right,
+                absolute: None,
+            };
+            *state = PointerState {
+                left: state.left,
+                right: state.right,
+                ..PointerState::default()
+            };
+            return Some(report);
+        }
+        _ => return None,
+    }
+    state.dirty = true;
+    None
+}
+
+/// Shared implementation of `mod list|load <archivo>|unload <nombre>|dev`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let words: Vec<&str> = args.split_whitespace().collect();
+    match words.as_slice() {
+        [] | ["list"] => {
+            let modules = MODULES.lock();
+            if modules.is_empty() {
+                out.push(alloc::format!("mod: ningun modulo cargado (ABI {}).", ABI_VERSION));
+            }
+            let devices = DEVICES.lock();
+            for module in modules.iter() {
+                let owned = devices.iter().filter(|d| d.module == module.id).count();
+                out.push(alloc::format!(
+                    "  {:<16} {:>6} KiB ({} KiB codigo) {} dispositivo(s)  {}{}",
+                    module.name,
+                    module.image.len().div_ceil(1024),
+                    module.code_len.div_ceil(1024),
+                    owned,
+                    module.path,
+                    if module.exit.is_some() { "" } else { "  [sin rko_exit]" }
+                ));
+            }
+        }
+        ["load", path] => match load(path) {
+            Ok(name) => {
+                let id = MODULES
+                    .lock()
+                    .iter()
+                    .find(|m| m.name == name)
+                    .map(|m| m.id)
+                    .unwrap_or(0);
+                let count = DEVICES.lock().iter().filter(|d| d.module == id).count();
+                out.push(alloc::format!(
+                    "mod: '{}' cargado, {} dispositivo(s) registrado(s).",
+                    name,
+                    count
+                ));
+            }
+            Err(e) => out.push(alloc::format!("mod: {}", e)),
+        },
+        ["unload", name] => match unload(name) {
+            Ok(removed) => out.push(alloc::format!(
+                "mod: '{}' descargado ({} dispositivo(s) retirados).",
+                name,
+                removed
+            )),
+            Err(e) => out.push(alloc::format!("mod: {}", e)),
+        },
+        ["dev"] => {
+            let devices = DEVICES.lock();
+            let modules = MODULES.lock();
+            if devices.is_empty() {
+                out.push(String::from("mod: ningun dispositivo de modulos."));
+            }
+            for (index, device) in devices.iter().enumerate() {
+                let owner = modules
+                    .iter()
+                    .find(|m| m.id == device.module)
+                    .map(|m| m.name.as_str())
+                    .unwrap_or("?");
+                let detail = match device.ops {
+                    DeviceOps::Block(ops) => alloc::format!(
+                        "{} bloques de {} bytes{}",
+                        ops.block_count,
+                        ops.block_size,
+                        if ops.write.is_some() { "" } else { ", solo lectura" }
+                    ),
+                    DeviceOps::Net(ops) => alloc::format!(
+                        "MAC {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x} MTU {}",
+                        ops.mac[0],
+                        ops.mac[1],
+                        ops.mac[2],
+                        ops.mac[3],
+                        ops.mac[4],
+                        ops.mac[5],
+                        ops.mtu
+                    ),
+                    DeviceOps::Input(_) => String::from("eventos evdev"),
+                };
+                out.push(alloc::format!(
+                    "  {:>2} {:<5} {:<12} {} ({})",
+                    index,
+                    device.ops.class(),
+                    device.name,
+                    detail,
+                    owner
+                ));
+            }
+        }
+        _ => out.push(String::from("Uso: mod [list|load <archivo.rko>|unload <nombre>|dev]")),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "kmod";
+
+    fn applies_relocations() {
+        let mut image = [0u8; 16];
+        crate::selftest::ensure(relocate(&mut image, 0, 0x1000, R_X86_64_64, 0x2000, 8, None).is_ok(), "R_X86_64_64")?;
+        crate::selftest::ensure_eq(read_u64(&image, 0), Some(0x2008), "absoluta")?;
+        crate::selftest::ensure(relocate(&mut image, 8, 0x1008, R_X86_64_PLT32, 0x1000, -4, None).is_ok(), "PLT32")?;
+        crate::selftest::ensure_eq(read_u32(&image, 8), Some((-12i32) as u32), "relativa hacia atras")?;
+        crate::selftest::ensure(
+            relocate(&mut image, 8, 0x1008, R_X86_64_REX_GOTPCRELX, 0xdead, -4, Some(0x1100)).is_ok(),
+            "GOTPCRELX",
+        )?;
+        crate::selftest::ensure_eq(read_u32(&image, 8), Some(0xF4), "relativa a la GOT")?;
+        crate::selftest::ensure(relocate(&mut image, 8, 0x1008, R_X86_64_32, 1 << 40, 0, None).is_err(), "32 fuera de rango")?;
+        crate::selftest::ensure(relocate(&mut image, 12, 0, R_X86_64_64, 0, 0, None).is_err(), "fuera de la seccion")?;
+        crate::selftest::ensure(relocate(&mut image, 0, 0, 37, 0, 0, None).is_err(), "tipo desconocido")
+    }
+
+    fn parses_modinfo() {
+        let info = parse_modinfo(b"name=e1000\0abi=1\0\0basura\0");
+        crate::selftest::ensure_eq(info, alloc::vec![("name", "e1000"), ("abi", "1")], "modinfo")
+    }
+
+    fn reports_pointer_on_syn() {
+        let mut state = PointerState::default();
+        let ev = |kind, code, value| RkoInputEvent { kind, code, value };
+        crate::selftest::ensure(handle_input(&mut state, ev(EV_REL, REL_X, 5)).is_none(), "sin SYN")?;
+        crate::selftest::ensure(handle_input(&mut state, ev(EV_KEY, BTN_LEFT, 1)).is_none(), "boton")?;
+        let report = handle_input(&mut state, ev(EV_SYN, 0, 0)).ok_or(String::from("sin informe"))?;
+        crate::selftest::ensure_eq((report.dx, report.left), (5, true), "informe")?;
+        crate::selftest::ensure(handle_input(&mut state, ev(EV_SYN, 0, 0)).is_none(), "SYN vacio")
+    }
+
+    fn loads_and_unloads_objects() {
+        use sha2::Digest;
+        let object = test_object();
+        crate::selftest::ensure(Object::parse(&object[..40]).is_err(), "truncado")?;
+        let mut signature = String::from("REDUX-SIG-V1\nALGO=sha256\nSHA256=");
+        for byte in sha2::Sha256::digest(&object).iter() {
+            signature.push_str(alloc::format!("{:02x}", byte).as_str());
+        }
+        crate::selftest::ensure(
+            load_image("prueba", "/selftest", &object[..object.len() - 1], Some(&signature)).is_err(),
+            "firma que no cuadra",
+        )?;
+        let name = load_image("prueba", "/selftest", object.as_slice(), Some(&signature))
+            .map_err(|e| alloc::format!("carga: {}", e))?;
+        crate::selftest::ensure_eq(name.as_str(), "selftest_mod", "nombre de .modinfo")?;
+        let (base, data) = {
+            let modules = MODULES.lock();
+            let module = modules.iter().find(|m| m.name == "selftest_mod").ok_or(String::from("no listado"))?;
+            (module.image.as_ptr() as u64, read_u64(module.image.as_slice(), PAGE))
+        };
+        crate::selftest::ensure_eq(data, Some(base), ".data apunta a rko_init")?;
+        crate::selftest::ensure(
+            load_image("prueba", "/selftest", object.as_slice(), Some(&signature)).is_err(),
+            "duplicado",
+        )?;
+        crate::selftest::ensure_eq(unload("selftest_mod"), Ok(0), "descarga")?;
+        crate::selftest::ensure(unload("selftest_mod").is_err(), "ya descargado")
+    }
+}
+
+/// A minimal module: `rko_init` returns 0 and `.data` holds its address.
+#[cfg(feature = "selftest")]
+fn test_object() -> Vec<u8> {
+    fn put(out: &mut Vec<u8>, words: &[(u64, usize)]) {
+        for (value, size) in words {
+            out.extend_from_slice(&value.to_le_bytes()[..*size]);
+        }
+    }
+    let text = [0x31u8, 0xC0, 0xC3, 0x90];
+    let modinfo = b"name=selftest_mod\0abi=1\0";
+    let strtab = b"\0rko_init\0";
+    let shstrtab = b"\0.text\0.data\0.rela.data\0.symtab\0.strtab\0.shstrtab\0.modinfo\0";
+    let mut body = Vec::new();
+    let text_at = 64 + body.len();
+    body.extend_from_slice(&text);
+    let data_at = 64 + body.len();
+    body.extend_from_slice(&[0u8; 8]);
+    let rela_at = 64 + body.len();
+    put(&mut body, &[(0, 8), ((1 << 32) | R_X86_64_64 as u64, 8), (0, 8)]);
+    let symtab_at = 64 + body.len();
+    body.extend_from_slice(&[0u8; 24]);
+    put(&mut body, &[(1, 4), (0x12, 1), (0, 1), (1, 2), (0, 8), (3, 8)]);
+    let strtab_at = 64 + body.len();
+    body.extend_from_slice(strtab);
+    let shstrtab_at = 64 + body.len();
+    body.extend_from_slice(shstrtab);
+    let modinfo_at = 64 + body.len();
+    body.extend_from_slice(modinfo);
+    while body.len() % 8 != 0 {
+        body.push(0);
+    }
+    let shoff = 64 + body.len();
+
+    let mut out = Vec::new();
+    out.extend_from_slice(b"\x7fELF\x02\x01\x01");
+    out.resize(16, 0);
+    put(
+        &mut out,
+        &[
+            (ET_REL as u64, 2),
+            (EM_X86_64 as u64, 2),
+            (1, 4),
+            (0, 8),
+            (0, 8),
+            (shoff as u64, 8),
+        ],
+    );
+    put(&mut out, &[(0, 4), (64, 2), (0, 2), (0, 2), (64, 2), (8, 2), (6, 2)]);
+    out.extend_from_slice(&body);
+    // name, type, flags, offset, size, link, info, align, entsize
+    let sections: [(usize, u32, u64, usize, usize, u32, u32, u64, u64); 8] = [
+        (0, 0, 0, 0, 0, 0, 0, 0, 0),
+        (
+            1,
+            SHT_PROGBITS,
+            SHF_ALLOC | SHF_EXECINSTR,
+            text_at,
+            text.len(),
+            0,
+            0,
+            16,
+            0,
+        ),
+        (7, SHT_PROGBITS, SHF_ALLOC | SHF_WRITE, data_at, 8, 0, 0, 8, 0),
+        (13, SHT_RELA, 0, rela_at, 24, 4, 2, 8, 24),
+        (24, SHT_SYMTAB, 0, symtab_at, 48, 5, 1, 8, 24),
+        (32, 3, 0, strtab_at, strtab.len(), 0, 0, 1, 0),
+        (40, 3, 0, shstrtab_at, shstrtab.len(), 0, 0, 1, 0),
+        (50, SHT_PROGBITS, 0, modinfo_at, modinfo.len(), 0, 0, 1, 0),
+    ];
+    for (name, kind, flags, offset, size, link, info, align, entsize) in sections {
+        put(
+            &mut out,
+            &[
+                (name as u64, 4),
+                (kind as u64, 4),
+                (flags, 8),
+                (0, 8),
+                (offset as u64, 8),
+                (size as u64, 8),
+                (link as u64, 4),
+                (info as u64, 4),
+                (align, 8),
+                (entsize, 8),
+            ],
+        );
+    }
+    out
+}
@@ -20,6 +20,7 @@ const PT_LOAD: u32 = 1;
 const PT_DYNAMIC: u32 = 2;
 const PT_INTERP: u32 = 3;
 const PT_TLS: u32 = 7;
+const PF_X: u32 = 1;
 
 const DT_NULL: i64 = 0;
 const DT_NEEDED: i64 = 1;
@@ -135,7 +136,7 @@ pub struct Phase2DynamicStageReport {
 pub struct RuntimeDynImage {
     pub report: ElfInspectReport,
     pub dyn_info: RuntimeRelocDynamicInfo,
-    pub image: Vec<u8>,
+    pub image: crate::wx::PageBuffer,
     pub phdr_blob: Vec<u8>,
     pub tls_block: Vec<u8>,
     tls_tcb_addr: u64,
@@ -153,6 +154,38 @@ pub struct RuntimeDynImage {
     reloc_errors: u32,
 }
 
+impl RuntimeDynImage {
+    /// W^X for the staged image: `PF_X` load segments become read-only and
+    /// executable, the rest stays writable and non-executable. Returns how
+    /// many segments are executable.
+    pub fn protect_segments(&self) -> usize {
+        let phent = self.phent as usize;
+        let mut executable = 0usize;
+        for index in 0..self.phnum as usize {
+            let off = index * phent;
+            let (Some(p_type), Some(p_flags), Some(vaddr), Some(mem_size)) = (
+                read_u32_le_at(&self.phdr_blob, off),
+                read_u32_le_at(&self.phdr_blob, off + 4),
+                read_u64_le_at(&self.phdr_blob, off + 16),
+                read_u64_le_at(&self.phdr_blob, off + 40),
+            ) else {
+                break;
+            };
+            if p_type != PT_LOAD || p_flags & PF_X == 0 {
+                continue;
+            }
+            let Some(start) = vaddr.checked_sub(self.report.span_start) else {
+                continue;
+            };
+            let end = start.saturating_add(mem_size);
+            if self.image.make_executable(start as usize, end.min(usize::MAX as u64) as usize) {
+                executable += 1;
+            }
+        }
+        executable
+    }
+}
+
 pub struct LinuxDynLaunchPlan {
     pub main_base: u64,
     pub main_entry: u64,
@@ -1787,7 +1820,7 @@ fn stage_runtime_dyn_image(raw: &[u8]) -> Result<RuntimeDynImage, &'static str>
     }
 
     let image_size = u64_to_usize(span_size).ok_or("runtime phase2: span fuera de rango.")?;
-    let mut image = alloc::vec![0u8; image_size];
+    let mut image = crate::wx::PageBuffer::zeroed(image_size).ok_or("runtime phase2: sin memoria para la imagen.")?;
 
     for seg in report.load_segments.iter() {
         if seg.file_size == 0 {
@@ -2103,9 +2136,12 @@ fn build_linux_initial_stack(
     execfn: &str,
     extra_env_items: &[&str],
 ) -> Result<(Vec<u8>, u64, usize, usize, usize), &'static str> {
-    let mut stack = alloc::vec![0u8; LINUX_STACK_SIZE];
+    // ASLR: the top `gap` bytes stay unused, so the stack pointer moves
+    // while the usable size stays LINUX_STACK_SIZE.
+    let gap = crate::aslr::stack_gap();
+    let mut stack = alloc::vec![0u8; LINUX_STACK_SIZE + gap];
     let stack_base = stack.as_ptr() as usize as u64;
-    let mut top = stack.len();
+    let mut top = stack.len() - gap;
 
     let argv0_default = argv_items
         .first()
@@ -2191,15 +2227,7 @@ fn build_linux_initial_stack(
     }
 
     let mut random_seed = [0u8; 16];
-    let mut seed = (main.sample_hash as u64) << 32 | (interp.sample_hash as u64);
-    let mut i = 0usize;
-    while i < random_seed.len() {
-        seed ^= seed >> 12;
-        seed ^= seed << 25;
-        seed ^= seed >> 27;
-        random_seed[i] = (seed.wrapping_mul(0x2545F4914F6CDD1D) & 0xFF) as u8;
-        i += 1;
-    }
+    crate::random::fill(&mut random_seed);
     let random_ptr = stack_place_aligned_bytes(
         stack.as_mut_slice(),
         stack_base,
@@ -2377,6 +2405,8 @@ fn prepare_phase2_interp_launch_with_deps_core(
         )?;
 
     let tls_tcb_addr = main_image.tls_tcb_addr;
+    main_image.protect_segments();
+    interp_image.protect_segments();
 
     Ok(LinuxDynLaunchPlan {
         main_base: main_image.load_bias,
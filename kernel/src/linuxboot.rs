@@ -0,0 +1,347 @@
+//! Boots a plain `vmlinuz` with its initramfs through the kernel's EFI stub,
+//! without a loader on the volume.
+//!
+//! Since 5.8 the stub asks for the initrd over LoadFile2 on a handle whose
+//! device path is the vendor media node LINUX_EFI_INITRD_MEDIA_GUID. `boot`
+//! reads the initrds, installs that handle for as long as the kernel runs and
+//! serves their concatenation from it. The command line still carries
+//! `initrd=` for older kernels; newer ones prefer LoadFile2 and ignore it.
+//!
+//! The command line comes from the `linux.cmdline` setting (`ro` when
+//! unset). Before GLOBAL_FAT is mounted it is read from the boot volume, so
+//! the boot menu honours it too.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::c_void;
+
+use uefi::boot;
+use uefi::fs::FileSystem as UefiFileSystem;
+use uefi::proto::device_path::DevicePath;
+use uefi::proto::media::fs::SimpleFileSystem;
+use uefi::{CString16, Guid, Handle, Identify, Status};
+
+use crate::config::{self, ConfigValue};
+use crate::osprober::{Boot, Target};
+use crate::spinlock::SpinLock;
+
+pub const CMDLINE_KEY: &str = "linux.cmdline";
+pub const DEFAULT_CMDLINE: &str = "ro";
+/// Where a guest keeps a plain kernel when it has no EFI stub image;
+/// `osprober` already covers `\` and `\boot`.
+pub const GUEST_DIRS: [&str; 2] = ["\\EFI\\LINUX", "\\LINUX"];
+
+const LOAD_FILE2_GUID: Guid = uefi::guid!("4006c0c1-fcb3-403e-996d-4a6c8724e06d");
+const LINUX_EFI_INITRD_MEDIA_GUID: Guid = uefi::guid!("5568e427-68fc-4f3d-ac74-ca555231cc68");
+
+#[repr(C)]
+struct RawLoadFile2 {
+    load_file: unsafe extern "efiapi" fn(
+        this: *const RawLoadFile2,
+        file_path: *const c_void,
+        boot_policy: bool,
+        buffer_size: *mut usize,
+        buffer: *mut c_void,
+    ) -> Status,
+}
+
+static PROVIDER: RawLoadFile2 = RawLoadFile2 { load_file: load_initrd };
+/// VenMedia(LINUX_EFI_INITRD_MEDIA_GUID) followed by the end node.
+static INITRD_DEVICE_PATH: [u8; 24] = initrd_device_path();
+/// The initrds of the kernel being started, back to back.
+static INITRD: SpinLock<Vec<u8>> = SpinLock::new(Vec::new());
+
+const fn initrd_device_path() -> [u8; 24] {
+    let guid = LINUX_EFI_INITRD_MEDIA_GUID.to_bytes();
+    let mut out = [0u8; 24];
+    out[0] = 0x04; // media
+    out[1] = 0x03; // vendor
+    out[2] = 20;
+    let mut i = 0;
+    while i < 16 {
+        out[4 + i] = guid[i];
+        i += 1;
+    }
+    out[20] = 0x7f;
+    out[21] = 0xff;
+    out[22] = 4;
+    out
+}
+
+/// Copy `data` into `buffer`. `Err` carries the size needed when the buffer
+/// is missing or too small.
+pub fn copy_initrd(data: &[u8], buffer: Option<&mut [u8]>) -> Result<usize, usize> {
+    match buffer {
+        Some(buffer) if buffer.len() >= data.len() => {
+            buffer[..data.len()].copy_from_slice(data);
+            Ok(data.len())
+        }
+        _ => Err(data.len()),
+    }
+}
+
+unsafe extern "efiapi" fn load_initrd(
+    _this: *const RawLoadFile2,
+    file_path: *const c_void,
+    boot_policy: bool,
+    buffer_size: *mut usize,
+    buffer: *mut c_void,
+) -> Status {
+    if file_path.is_null() || buffer_size.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    if boot_policy {
+        return Status::UNSUPPORTED;
+    }
+    let data = INITRD.lock();
+    if data.is_empty() {
+        return Status::NOT_FOUND;
+    }
+    let buffer = if buffer.is_null() {
+        None
+    } else {
+        Some(core::slice::from_raw_parts_mut(buffer.cast::<u8>(), *buffer_size))
+    };
+    match copy_initrd(data.as_slice(), buffer) {
+        Ok(copied) => {
+            *buffer_size = copied;
+            Status::SUCCESS
+        }
+        Err(needed) => {
+            *buffer_size = needed;
+            Status::BUFFER_TOO_SMALL
+        }
+    }
+}
+
+/// Concatenate initrds the way the kernel unpacks them: each archive starts
+/// on a 4-byte boundary, the gap filled with zeros.
+pub fn join_initrds(parts: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for part in parts {
+        out.resize(out.len().next_multiple_of(4), 0);
+        out.extend_from_slice(part.as_slice());
+    }
+    out
+}
+
+/// The handle the stub finds the initrd on; removed by `Drop`.
+struct InitrdHandle(Handle);
+
+impl InitrdHandle {
+    fn install(initrd: Vec<u8>) -> Result<Self, String> {
+        *INITRD.lock() = initrd;
+        let path = INITRD_DEVICE_PATH.as_ptr().cast::<c_void>();
+        let handle = unsafe { boot::install_protocol_interface(None, &DevicePath::GUID, path) }
+            .map_err(|err| alloc::format!("no se pudo instalar la ruta del initrd: {:?}", err))?;
+        let provider = (&PROVIDER as *const RawLoadFile2).cast::<c_void>();
+        if let Err(err) = unsafe { boot::install_protocol_interface(Some(handle), &LOAD_FILE2_GUID, provider) } {
+            let _ = unsafe { boot::uninstall_protocol_interface(handle, &DevicePath::GUID, path) };
+            INITRD.lock().clear();
+            return Err(alloc::format!("no se pudo instalar LoadFile2: {:?}", err));
+        }
+        Ok(Self(handle))
+    }
+}
+
+impl Drop for InitrdHandle {
+    fn drop(&mut self) {
+        let provider = (&PROVIDER as *const RawLoadFile2).cast::<c_void>();
+        let path = INITRD_DEVICE_PATH.as_ptr().cast::<c_void>();
+        unsafe {
+            let _ = boot::uninstall_protocol_interface(self.0, &LOAD_FILE2_GUID, provider);
+            let _ = boot::uninstall_protocol_interface(self.0, &DevicePath::GUID, path);
+        }
+        *INITRD.lock() = Vec::new();
+    }
+}
+
+/// The `linux.cmdline` setting, from the boot volume while the config store
+/// is not mounted yet.
+pub fn command_line() -> String {
+    if let Some(ConfigValue::Str(line)) = config::get(CMDLINE_KEY) {
+        return line;
+    }
+    if !config::is_persistent() {
+        if let Some(current) = crate::current_boot_device_handle() {
+            if let Some(ConfigValue::Str(line)) = crate::boot_config_registry(current).get(CMDLINE_KEY) {
+                return line.clone();
+            }
+        }
+    }
+    String::from(DEFAULT_CMDLINE)
+}
+
+fn efi_path(path: &str) -> Result<CString16, String> {
+    CString16::try_from(path.replace('/', "\\").as_str()).map_err(|_| alloc::format!("ruta invalida: {}", path))
+}
+
+/// Start `kernel` on the volume `handle` with `initrds` from the same volume
+/// and `options`. Returns when the kernel exits, which a Linux kernel only
+/// does when its stub fails.
+pub fn boot(handle: Handle, kernel: &str, initrds: &[String], options: &str) -> Result<(), String> {
+    let mut parts = Vec::new();
+    for initrd in initrds {
+        let data = crate::read_file_from_fs_handle(handle, &efi_path(initrd)?)
+            .ok_or_else(|| alloc::format!("no se pudo leer {}", initrd))?;
+        parts.push(data);
+    }
+    let line = crate::bootmenu::kernel_command_line(initrds, options);
+    let line = CString16::try_from(line.as_str()).map_err(|_| String::from("linea de comandos invalida"))?;
+    let _initrd = if parts.is_empty() {
+        None
+    } else {
+        Some(InitrdHandle::install(join_initrds(parts.as_slice()))?)
+    };
+    crate::start_image_at(handle, &efi_path(kernel)?, Some(&line))
+}
+
+/// Plain kernels in the guest directories of the volume `handle`.
+pub fn guest_kernels(handle: Handle, options: &str) -> Vec<Target> {
+    let Ok(proto) = boot::open_protocol_exclusive::<SimpleFileSystem>(handle) else {
+        return Vec::new();
+    };
+    let mut fs = UefiFileSystem::new(proto);
+    let mut out = Vec::new();
+    for dir in GUEST_DIRS {
+        let files = crate::osprober::files_in(&mut fs, dir);
+        out.extend(crate::osprober::kernel_targets(dir, files.as_slice(), options));
+    }
+    out
+}
+
+/// Every plain kernel the firmware can reach: the ones `osprober` finds and
+/// the ones in the guest directories.
+fn kernels() -> Vec<(Handle, Target)> {
+    let options = command_line();
+    let mut out: Vec<(Handle, Target)> = crate::osprober::probe_handles()
+        .into_iter()
+        .filter(|(_, target)| matches!(target.boot, Boot::Linux { .. }))
+        .collect();
+    let Ok(handles) = boot::find_handles::<SimpleFileSystem>() else {
+        return out;
+    };
+    for handle in handles.iter().copied() {
+        if crate::handle_has_installed_redux_marker(handle) {
+            continue;
+        }
+        out.extend(
+            guest_kernels(handle, options.as_str())
+                .into_iter()
+                .map(|target| (handle, target)),
+        );
+    }
+    out
+}
+
+fn describe(target: &Target) -> String {
+    match &target.boot {
+        Boot::Linux {
+            kernel,
+            initrds,
+            options,
+        } => {
+            alloc::format!(
+                "{} -> {} {}",
+                target.title,
+                kernel,
+                crate::bootmenu::kernel_command_line(initrds, options)
+            )
+        }
+        _ => target.title.clone(),
+    }
+}
+
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim();
+    let (sub, rest) = args.split_once(' ').unwrap_or((args, ""));
+    let rest = rest.trim();
+    let mut lines = Vec::new();
+    match sub {
+        "" | "list" => {
+            let found = kernels();
+            if found.is_empty() {
+                lines.push(String::from("linuxboot: no se encontraron nucleos vmlinuz"));
+            }
+            for (index, (_, target)) in found.iter().enumerate() {
+                lines.push(alloc::format!("{}) {}", index + 1, describe(target)));
+            }
+        }
+        "boot" => {
+            let (index, options) = rest.split_once(' ').unwrap_or((rest, ""));
+            let found = kernels();
+            let chosen = index
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| index.checked_sub(1))
+                .and_then(|index| found.get(index));
+            let Some((handle, target)) = chosen else {
+                lines.push(String::from("Uso: linuxboot boot <n> [linea de comandos]"));
+                return lines;
+            };
+            let Boot::Linux {
+                kernel,
+                initrds,
+                options: configured,
+            } = &target.boot
+            else {
+                return lines;
+            };
+            let options = if options.trim().is_empty() {
+                configured.as_str()
+            } else {
+                options.trim()
+            };
+            crate::println(alloc::format!("linuxboot: arrancando {}...", target.title).as_str());
+            match boot(*handle, kernel.as_str(), initrds.as_slice(), options) {
+                Ok(()) => lines.push(alloc::format!("linuxboot: {} termino y regreso", target.title)),
+                Err(err) => lines.push(alloc::format!(
+                    "linuxboot: no se pudo arrancar {}: {}",
+                    target.title,
+                    err
+                )),
+            }
+        }
+        "cmdline" if rest.is_empty() => {
+            lines.push(alloc::format!("{} = {}", CMDLINE_KEY, command_line()));
+        }
+        "cmdline" => match config::set(CMDLINE_KEY, ConfigValue::Str(String::from(rest))) {
+            Ok(()) => lines.push(alloc::format!("{} = {}", CMDLINE_KEY, rest)),
+            Err(err) => lines.push(alloc::format!("linuxboot: {}", err)),
+        },
+        _ => lines.push(String::from("Uso: linuxboot [list|boot <n> [linea]|cmdline [texto]]")),
+    }
+    lines
+}
+
+crate::selftest::kernel_tests! {
+    "linuxboot";
+
+    fn serves_initrd_in_two_calls() {
+        let data = [1u8, 2, 3, 4, 5];
+        crate::selftest::ensure_eq(copy_initrd(&data, None), Err(5), "sin buffer")?;
+        let mut short = [0u8; 4];
+        crate::selftest::ensure_eq(copy_initrd(&data, Some(&mut short)), Err(5), "buffer corto")?;
+        let mut buffer = [0u8; 8];
+        crate::selftest::ensure_eq(copy_initrd(&data, Some(&mut buffer)), Ok(5), "copia")?;
+        crate::selftest::ensure_eq(&buffer[..5], &data[..], "contenido")
+    }
+
+    fn pads_concatenated_initrds() {
+        let parts = [alloc::vec![0xAAu8; 6], alloc::vec![0xBBu8; 3]];
+        let joined = join_initrds(&parts);
+        crate::selftest::ensure_eq(joined.len(), 11, "longitud")?;
+        crate::selftest::ensure_eq(&joined[6..8], &[0u8, 0][..], "relleno")?;
+        crate::selftest::ensure_eq(joined[8], 0xBB, "segundo inicia alineado")
+    }
+
+    fn builds_initrd_device_path() {
+        crate::selftest::ensure_eq(&INITRD_DEVICE_PATH[..4], &[4u8, 3, 20, 0][..], "nodo vendor")?;
+        crate::selftest::ensure_eq(
+            &INITRD_DEVICE_PATH[4..20],
+            &LINUX_EFI_INITRD_MEDIA_GUID.to_bytes()[..],
+            "guid",
+        )?;
+        crate::selftest::ensure_eq(&INITRD_DEVICE_PATH[20..], &[0x7fu8, 0xff, 4, 0][..], "fin")
+    }
+}
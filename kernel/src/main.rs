@@ -7,8 +7,34 @@ extern crate alloc;
 
 mod framebuffer;
 mod font;
+mod fbcon;
 mod hal;
 mod input;
+mod gamepad;
+mod usbhid;
+mod usb;
+mod perf;
+mod crypto;
+mod random;
+mod aslr;
+mod stack_guard;
+mod driver_guard;
+mod wx;
+mod uaccess;
+mod secureboot;
+mod tpm;
+mod crypt;
+mod bootvar;
+mod osprober;
+mod bootmenu;
+mod recovery;
+mod linuxboot;
+mod checkpoint;
+mod replay;
+mod bench;
+mod kmod;
+mod device;
+mod sysfs;
 mod interrupts;
 mod memory;
 pub mod paging;
@@ -16,17 +42,53 @@ mod process;
 mod privilege;
 mod runtime;
 mod scheduler;
+mod boottime;
+mod sysmon;
+mod trace;
 mod worker_pool;
 mod syscall;
 mod timer;
+mod clocksource;
+mod executor;
+mod procenv;
+mod touch;
+mod gamma;
+mod print;
+mod ttf;
+mod pdf;
+mod calc;
+mod calendar;
+mod alarm;
+mod search_index;
+mod fswatch;
+mod fsmeta;
+mod perm;
+mod fsjournal;
+mod chkdsk;
+mod klog;
+mod compress;
+mod archive;
+mod pkg;
+mod config;
+mod i18n;
 mod ui;
 mod usermode;
 mod pci;
+mod pci_ids;
+mod testharness;
+mod selftest;
+mod hwinfo;
 mod virtio;
 mod nvme;
 mod xhci;
+mod thunderbolt;
 mod audio;
+mod capture;
+mod synth;
 mod acpi;
+mod pm;
+mod cmdline;
+mod safemode;
 mod wav;
 pub mod net;
 mod intel_xe;
@@ -34,8 +96,10 @@ pub mod intel_net;
 pub mod intel_wifi;
 mod quota;
 mod fs;
+mod hostfs;
 mod fat32;
 mod allocator;
+mod heapcheck;
 mod gui;
 mod preboot_installer;
 mod web_engine;
@@ -63,6 +127,7 @@ mod ruby_runtime;
 mod linux_compat;
 mod linux_sysent;
 mod spinlock;
+mod sync;
 mod per_core;
 mod smp;
 
@@ -83,7 +148,7 @@ use uefi::CString16;
 
 const LOOP_STALL_US: usize = 10_000;
 const LINE_MAX: usize = 128;
-const UEFI_LOAD_OPTION_ACTIVE: u32 = 0x0000_0001;
+pub(crate) const UEFI_LOAD_OPTION_ACTIVE: u32 = 0x0000_0001;
 const DESKTOP_FRAME_STALL_US_DEFAULT: u64 = 650;
 const DESKTOP_FRAME_STALL_US_MIN: u64 = 200;
 const DESKTOP_FRAME_STALL_US_MAX: u64 = 1_500;
@@ -118,16 +183,47 @@ fn efi_main() -> Status {
     crate::runtime::set_runtime_uefi_active(true);
     
     allocator::init_heap();
+    trace::instant("boot", "heap_ready", "");
+    clocksource::init();
+    klog::start_irq_drain();
+    i18n::init();
+    gamepad::init();
+    touch::init();
+    perf::init();
+    boottime::init();
+    gui::event_queue::init();
+    gui::interaction::init();
+    gui::osk::init();
+    gamma::init();
+    search_index::init();
+    fsmeta::init();
+    perm::init();
+    load_boot_locale();
+    let boot_options = boot_load_options();
+    // CMDLINE.TXT plus the load options; the switches below all read this.
+    cmdline::load(current_boot_device_handle(), boot_options.as_deref());
+    let boot_text = cmdline::text();
+    let boot_switches = cmdline::options();
+    let harness_mode = testharness::requested(Some(boot_text.as_str()), boot_media_has_test_marker());
+    let harness_selftest = boot_text.split_whitespace().any(|w| w.eq_ignore_ascii_case("selftest"));
+    if recovery::requested(Some(boot_text.as_str())) {
+        recovery::enter();
+    }
+    if boot_switches.safe_mode {
+        safemode::enter();
+    }
+    replay::note_load_options(boot_options.as_deref());
     maybe_rename_legacy_redux_boot_options();
     maybe_auto_register_installed_boot_option();
     maybe_ensure_redux_boot_priority();
 
     // Run preboot installer while UEFI storage/input stack is still pristine.
     // Custom PCI/NVMe init can interfere with firmware BlockIO protocols.
-    let installer_result = if should_skip_preboot_installer() {
+    let skip_installer = harness_mode || recovery::active() || safemode::active() || should_skip_preboot_installer();
+    let installer_result = if skip_installer {
         preboot_installer::InstallerResult::Skipped
     } else {
-        preboot_installer::run()
+        boottime::stage("preboot_installer", preboot_installer::run)
     };
     println("Kernel stage: installer returned.");
 
@@ -152,11 +248,20 @@ fn efi_main() -> Status {
         }
     }
     if matches!(installer_result, preboot_installer::InstallerResult::Skipped)
+        && !harness_mode
+        && !recovery::active()
         && should_show_boot_selector()
     {
-        maybe_handle_boot_selector();
+        bootmenu::run();
     }
-    if matches!(installer_result, preboot_installer::InstallerResult::Skipped) {
+    // Recovery mode stays on the text console from here on; `nogui` and a
+    // debug log level keep the boot messages visible instead of the splash.
+    if matches!(installer_result, preboot_installer::InstallerResult::Skipped)
+        && !recovery::active()
+        && !safemode::active()
+        && !boot_switches.nogui
+        && !cmdline::verbose_boot()
+    {
         unsafe { QUIET_BOOT = true; }
         clear_screen();
         if let Some(info) = capture_framebuffer_info() {
@@ -196,32 +301,78 @@ fn efi_main() -> Status {
         }
     }
 
-    let mem_status = memory::init_from_uefi();
-    let idt = interrupts::init_skeleton();
-    timer::init_polling(1); // 1ms per tick for GUI-based polling
-    scheduler::init_demo();
-    pci::scan();
-    smp::discover_cpus();
-    per_core::init();
-    smp::bootstrap_aps();
+    let mem_status = boottime::stage("memory", memory::init_from_uefi);
+    let idt = boottime::stage("interrupts", || {
+        let idt = interrupts::init_skeleton();
+        timer::init_polling(1); // 1ms per tick for GUI-based polling
+        scheduler::init_demo();
+        idt
+    });
+    // Before the APs start: their boot stacks get guard pages too.
+    boottime::stage("stack_guard", stack_guard::init);
+    // Also before the APs: firmware-run APs need EFER.NXE before any NX bit.
+    boottime::stage("wx", wx::init);
+    boottime::stage("uaccess", uaccess::init);
+    boottime::stage("heapcheck", heapcheck::init);
+    klog::start_file_log();
+    boottime::stage("pci_scan", pci::scan);
+    boottime::stage("smp", || {
+        smp::discover_cpus();
+        per_core::init();
+        smp::bootstrap_aps();
+    });
     
-    // Init network
-    net::init();
+    boottime::stage("crypto", crypto::init);
+    boottime::stage("random", random::init);
+    boottime::stage("tpm", tpm::init);
+    // Before anything mounts the data partition; the harness has no one to type.
+    boottime::stage("crypt", || crypt::unlock_at_boot(!harness_mode));
+
+    // Init network; recovery mode and `net=off` do without it.
+    if boot_switches.net == Some(cmdline::NetMode::Off) {
+        println("Net: disabled by boot option net=off.");
+    } else if !recovery::active() {
+        match boot_switches.net {
+            Some(cmdline::NetMode::Dhcp) => {
+                net::set_dhcp_mode();
+            }
+            Some(cmdline::NetMode::Static { ip, prefix, gateway }) => {
+                let _ = net::set_static_ipv4(ip, prefix, gateway);
+            }
+            _ => {}
+        }
+        boottime::stage("net_init", net::init);
+    }
     
-    quota::init();
-    quota::test_quota();
+    boottime::stage("quota", || {
+        quota::init();
+        quota::test_quota();
+    });
 
     println("Zenox OS UEFI Kernel - Phase 1+");
     println("x86_64 + OVMF | Rust no_std");
     println("");
 
+    // CI runs stay headless: the harness owns the shell until the host sends EXIT.
+    if harness_mode {
+        unsafe { QUIET_BOOT = false; }
+        testharness::run(0, harness_selftest);
+    }
+
     // If installer completed (or user skipped), continue directly to runtime GUI.
     // This avoids the "stuck screen" perception where VGA stays on installer UI
     // while shell prompt is only visible on serial.
+    if recovery::active() {
+        recovery::run();
+    }
     if matches!(installer_result, preboot_installer::InstallerResult::Skipped) {
-        if mem_status.is_ok() {
+        if safemode::active() {
+            println("Kernel stage: safe mode; staying in shell.");
+        } else if boot_switches.nogui {
+            println("Kernel stage: nogui boot option; staying in shell.");
+        } else if mem_status.is_ok() {
             println("Kernel stage: auto-launch GUI mode after installer.");
-            uefi::boot::stall(300_000);
+            settle_keys_before_gui();
             unsafe { QUIET_BOOT = false; }
             start_gui_mode();
         } else {
@@ -235,9 +386,9 @@ fn efi_main() -> Status {
             with_stdout(|out| {
                 let _ = writeln!(
                     out,
-                    "Memory map: regions={} conventional={} MiB",
+                    "Memory map: regions={} conventional={}",
                     stats.regions,
-                    stats.conventional_bytes() / (1024 * 1024)
+                    i18n::format::mib(stats.conventional_bytes())
                 );
             });
         }
@@ -258,6 +409,13 @@ fn efi_main() -> Status {
         );
     });
 
+    if safemode::active() {
+        println("");
+        for line in safemode::banner_lines().iter() {
+            println(line.as_str());
+        }
+        println("");
+    }
     println("Type 'help' to list commands.");
     println("Use 'boot' for bare metal runtime IRQ-safe (auto fallback), 'boot uefi' for USB keyboards.");
     println("Use 'boot poll' for forced polling mode.");
@@ -317,116 +475,6 @@ fn should_show_boot_selector() -> bool {
     detect_linux_guest_boot_target(Some(current), installed_handle).is_some()
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum BootSelectorChoice {
-    BootRedux(usize),
-    BootLinuxGuest,
-    BootOtherOs,
-}
-
-fn maybe_handle_boot_selector() {
-    let current_handle = current_boot_device_handle();
-    let installed_handles = find_installed_redux_handles(None);
-    let installed_handle = installed_handles.first().copied();
-    let has_linux_guest = detect_linux_guest_boot_target(current_handle, installed_handle).is_some();
-    let has_other_os = detect_other_os_boot_target(current_handle, installed_handle).is_some()
-        || find_windows_boot_option_id().ok().flatten().is_some();
-
-    if installed_handles.is_empty() && !has_other_os && !has_linux_guest {
-        return;
-    }
-
-    unsafe { QUIET_BOOT = false; }
-    clear_screen();
-    println("Zenox OS Boot Manager");
-    
-    let mut next_option = 1u8;
-    if !installed_handles.is_empty() {
-        for (i, handle) in installed_handles.iter().copied().enumerate() {
-            let desc = installed_redux_handle_description(handle, i + 1);
-            println(alloc::format!("{}) {}", next_option, desc).as_str());
-            next_option = next_option.saturating_add(1);
-        }
-    } else {
-        println("1) Iniciar Zenox OS actual");
-        next_option = 2;
-    }
-    if has_linux_guest {
-        println(alloc::format!("{}) Iniciar Linux guest (apps Linux reales)", next_option).as_str());
-        next_option = next_option.saturating_add(1);
-    }
-    if has_other_os {
-        println(alloc::format!("{}) Iniciar otro sistema operativo", next_option).as_str());
-    }
-    if next_option > 2 || has_other_os {
-        let max_opt = if has_other_os { next_option } else { next_option.saturating_sub(1) };
-        println(alloc::format!("Pulsa 1-{} (Enter=actual, Esc=actual).", max_opt).as_str());
-    } else {
-        println("Pulsa 1 (Enter=actual, Esc=actual).");
-    }
-
-    let default_redux_index = installed_handles
-        .iter()
-        .position(|handle| Some(*handle) == current_handle)
-        .unwrap_or(0);
-    let choice = read_boot_selector_choice(
-        has_linux_guest,
-        has_other_os,
-        installed_handles.len(),
-        default_redux_index,
-    );
-    match choice {
-        BootSelectorChoice::BootRedux(idx) => {
-            if !installed_handles.is_empty() {
-                let target_handle = installed_handles.get(idx).copied().or(installed_handles.first().copied());
-                if target_handle == current_handle {
-                    println(alloc::format!("Arranque: Zenox OS actual (Volumen {})...", idx + 1).as_str());
-                    uefi::boot::stall(350_000);
-                    clear_screen();
-                    return;
-                }
-                println(alloc::format!("Arranque: Zenox OS instalado (Volumen {})...", idx + 1).as_str());
-                match launch_installed_redux(target_handle) {
-                    Ok(path) => println(alloc::format!("Arranque regresó desde {}.", path).as_str()),
-                    Err(err) => {
-                        println(alloc::format!("No se pudo arrancar instalado: {}", err).as_str());
-                        uefi::boot::stall(2_500_000);
-                    }
-                }
-                println("Continuando con medio actual...");
-                uefi::boot::stall(400_000);
-            }
-            clear_screen();
-        }
-        BootSelectorChoice::BootLinuxGuest => {
-            println("Arranque: Linux guest...");
-            match launch_linux_guest_boot(current_handle, installed_handle) {
-                Ok(path) => println(alloc::format!("Arranque regresó desde {}.", path).as_str()),
-                Err(err) => {
-                    println(alloc::format!("No se pudo arrancar Linux guest: {}", err).as_str());
-                    uefi::boot::stall(2_500_000);
-                }
-            }
-            println("Continuando con medio actual...");
-            uefi::boot::stall(400_000);
-            clear_screen();
-        }
-        BootSelectorChoice::BootOtherOs => {
-            println("Arranque: otro sistema operativo...");
-            match launch_other_os_boot(current_handle, installed_handle) {
-                Ok(path) => println(alloc::format!("Arranque regresó desde {}.", path).as_str()),
-                Err(err) => {
-                    println(alloc::format!("No se pudo arrancar otro SO: {}", err).as_str());
-                    uefi::boot::stall(2_500_000);
-                }
-            }
-            println("Continuando con medio actual...");
-            uefi::boot::stall(400_000);
-            clear_screen();
-        }
-    }
-}
-
 fn maybe_auto_register_installed_boot_option() {
     let Some(current) = current_boot_device_handle() else {
         return;
@@ -440,57 +488,68 @@ fn maybe_auto_register_installed_boot_option() {
     }
 }
 
-fn read_boot_selector_choice(
-    has_linux_guest: bool,
-    has_other_os: bool,
-    redux_count: usize,
-    default_redux_index: usize,
-) -> BootSelectorChoice {
-    let mut waited_ticks = 0usize;
-    let timeout_ticks = 6000usize; // ~60s at 10ms polling
-    let redux_options = if redux_count > 0 { redux_count } else { 1 };
-    let default_redux_index = core::cmp::min(default_redux_index, redux_options.saturating_sub(1));
-    let guest_opt = redux_options + 1;
-    let other_opt = if has_linux_guest { guest_opt + 1 } else { guest_opt };
+pub(crate) fn current_boot_device_handle() -> Option<uefi::Handle> {
+    use uefi::boot;
+    use uefi::proto::loaded_image::LoadedImage;
 
-    loop {
-        if let Some(event) = poll_input_event() {
-            match event {
-                InputEvent::Enter | InputEvent::Escape => {
-                    return BootSelectorChoice::BootRedux(default_redux_index);
-                }
-                InputEvent::Char(c) => {
-                    if let Some(digit) = c.to_digit(10) {
-                        let opt = digit as usize;
-                        if opt > 0 && opt <= redux_options {
-                            return BootSelectorChoice::BootRedux(opt - 1);
-                        } else if opt == guest_opt && has_linux_guest {
-                            return BootSelectorChoice::BootLinuxGuest;
-                        } else if opt == other_opt && has_other_os {
-                            return BootSelectorChoice::BootOtherOs;
-                        }
-                    }
-                }
-                _ => {}
-            }
-        }
-        if waited_ticks >= timeout_ticks {
-            return BootSelectorChoice::BootRedux(default_redux_index);
-        }
-        uefi::boot::stall(10_000);
-        waited_ticks = waited_ticks.saturating_add(1);
-    }
+    let loaded = boot::open_protocol_exclusive::<LoadedImage>(boot::image_handle()).ok()?;
+    loaded.device()
 }
 
-fn current_boot_device_handle() -> Option<uefi::Handle> {
+fn boot_load_options() -> Option<String> {
     use uefi::boot;
     use uefi::proto::loaded_image::LoadedImage;
 
     let loaded = boot::open_protocol_exclusive::<LoadedImage>(boot::image_handle()).ok()?;
-    loaded.device()
+    let options = loaded.load_options_as_cstr16().ok()?;
+    Some(alloc::format!("{}", options))
+}
+
+fn boot_media_has_test_marker() -> bool {
+    let Some(current) = current_boot_device_handle() else {
+        return false;
+    };
+    read_file_from_fs_handle(current, uefi::cstr16!("\\REDUXTEST.INI")).is_some()
+}
+
+/// Pick the interface language before the installer and boot selector draw.
+/// GLOBAL_FAT is not mounted yet, so the config store is read straight from
+/// the boot volume; the `system.locale` watcher takes over once it mounts.
+fn load_boot_locale() {
+    let Some(current) = current_boot_device_handle() else {
+        return;
+    };
+    let registry = boot_config_registry(current);
+    let code = match registry.get(i18n::LOCALE_KEY) {
+        Some(config::ConfigValue::Str(code)) if i18n::valid_code(code.as_str()) => code.clone(),
+        _ => return,
+    };
+    let pack_path = alloc::format!("\\REDUXOS\\{}\\{}.TXT", i18n::LANG_DIR, code.to_ascii_uppercase());
+    let pack = CString16::try_from(pack_path.as_str())
+        .ok()
+        .and_then(|path| read_file_from_fs_handle(current, &path));
+    i18n::set_language(code.as_str(), pack.as_deref());
+}
+
+/// The config store on `handle` as the kernel will load it, for code that
+/// runs before GLOBAL_FAT is mounted.
+pub(crate) fn boot_config_registry(handle: uefi::Handle) -> config::Registry {
+    let mut registry = config::Registry::new();
+    let loaded = read_file_from_fs_handle(handle, uefi::cstr16!("\\REDUXOS\\CONFIG.BIN"))
+        .map(|raw| registry.load_snapshot(raw.as_slice()).is_ok())
+        .unwrap_or(false);
+    if !loaded {
+        if let Some(raw) = read_file_from_fs_handle(handle, uefi::cstr16!("\\REDUXOS\\CONFIG.OLD")) {
+            let _ = registry.load_snapshot(raw.as_slice());
+        }
+    }
+    if let Some(raw) = read_file_from_fs_handle(handle, uefi::cstr16!("\\REDUXOS\\CONFIG.JNL")) {
+        let _ = registry.replay_journal(raw.as_slice());
+    }
+    registry
 }
 
-fn read_file_from_fs_handle(handle: uefi::Handle, path: &uefi::CStr16) -> Option<Vec<u8>> {
+pub(crate) fn read_file_from_fs_handle(handle: uefi::Handle, path: &uefi::CStr16) -> Option<Vec<u8>> {
     use uefi::boot;
     use uefi::fs::FileSystem as UefiFileSystem;
     use uefi::proto::media::fs::SimpleFileSystem;
@@ -508,7 +567,7 @@ fn handle_is_removable(handle: uefi::Handle) -> Option<bool> {
     Some(blk.media().is_removable_media())
 }
 
-fn handle_has_installed_redux_marker(handle: uefi::Handle) -> bool {
+pub(crate) fn handle_has_installed_redux_marker(handle: uefi::Handle) -> bool {
     for marker in [uefi::cstr16!("\\GOOS.INI"), uefi::cstr16!("\\REDUXOS.INI"), uefi::cstr16!("\\ZENOXOS.INI")] {
         if let Some(bytes) = read_file_from_fs_handle(handle, marker) {
             let text = core::str::from_utf8(bytes.as_slice()).unwrap_or("");
@@ -531,6 +590,12 @@ fn handle_has_installed_redux_marker(handle: uefi::Handle) -> bool {
     false
 }
 
+/// After `reconnect_storage_controllers`, how long to keep looking for the
+/// volume. The old fixed 150 ms stall was too long for fast controllers and
+/// too short for some USB sticks.
+const STORAGE_RECONNECT_TIMEOUT_US: u64 = 500_000;
+const STORAGE_RECONNECT_POLL_US: u64 = 10_000;
+
 fn reconnect_storage_controllers() {
     use uefi::boot;
     use uefi::proto::media::block::BlockIO;
@@ -614,7 +679,7 @@ fn install_marker_u64_value(handle: uefi::Handle, key: &str) -> Option<u64> {
     None
 }
 
-fn installed_redux_handle_description(handle: uefi::Handle, ordinal: usize) -> String {
+pub(crate) fn installed_redux_handle_description(handle: uefi::Handle, ordinal: usize) -> String {
     let removable = handle_is_removable(handle).unwrap_or(false);
     let media = if removable { "USB" } else { "INTERNO" };
     let (partition_number, partition_start) = handle_partition_identity(handle);
@@ -667,7 +732,7 @@ fn installed_data_partition_description(data_start_lba: u64) -> Option<String> {
     None
 }
 
-fn find_installed_redux_handles(exclude: Option<uefi::Handle>) -> Vec<uefi::Handle> {
+pub(crate) fn find_installed_redux_handles(exclude: Option<uefi::Handle>) -> Vec<uefi::Handle> {
     use uefi::boot;
     use uefi::proto::media::fs::SimpleFileSystem;
 
@@ -712,68 +777,15 @@ fn find_installed_redux_handle_with_retry(exclude: Option<uefi::Handle>) -> Opti
     }
 
     reconnect_storage_controllers();
-    uefi::boot::stall(150_000);
-    find_installed_redux_handle(exclude)
+    boottime::wait_for(STORAGE_RECONNECT_TIMEOUT_US, STORAGE_RECONNECT_POLL_US, || {
+        find_installed_redux_handle(exclude)
+    })
 }
 
 fn is_probably_efi_image(bytes: &[u8]) -> bool {
     bytes.len() >= 4096 && bytes.len() <= 64 * 1024 * 1024 && bytes[0] == b'M' && bytes[1] == b'Z'
 }
 
-fn load_grub_payload_for_fallback(source_handle: uefi::Handle) -> Result<Vec<u8>, String> {
-    let target_candidates = [
-        uefi::cstr16!("\\EFI\\GRUB\\GRUBX64.EFI"),
-        uefi::cstr16!("\\EFI\\GRUB\\grubx64.efi"),
-    ];
-
-    for path in target_candidates.iter() {
-        if let Some(bytes) = read_file_from_fs_handle(source_handle, *path) {
-            if is_probably_efi_image(bytes.as_slice()) {
-                return Ok(bytes);
-            }
-        }
-    }
-
-    let redux_present =
-        read_file_from_fs_handle(source_handle, uefi::cstr16!("\\EFI\\BOOT\\REDUX64.EFI")).is_some();
-    if redux_present {
-        if let Some(bytes) =
-            read_file_from_fs_handle(source_handle, uefi::cstr16!("\\EFI\\BOOT\\BOOTX64.EFI"))
-        {
-            if is_probably_efi_image(bytes.as_slice()) {
-                return Ok(bytes);
-            }
-        }
-    }
-
-    use uefi::boot;
-    use uefi::fs::FileSystem as UefiFileSystem;
-
-    let fs_proto = boot::get_image_file_system(boot::image_handle())
-        .map_err(|err| alloc::format!("volumen de arranque no disponible: {:?}", err))?;
-    let mut fs = UefiFileSystem::new(fs_proto);
-    let boot_media_candidates = [
-        uefi::cstr16!("\\EFI\\GRUB\\GRUBX64.EFI"),
-        uefi::cstr16!("\\EFI\\GRUB\\grubx64.efi"),
-        uefi::cstr16!("\\EFI\\GOOS\\GRUBX64.EFI"),
-        uefi::cstr16!("\\EFI\\GOOS\\grubx64.efi"),
-        uefi::cstr16!("\\EFI\\REDUXOS\\GRUBX64.EFI"),
-        uefi::cstr16!("\\EFI\\REDUXOS\\grubx64.efi"),
-        uefi::cstr16!("\\GRUBX64.EFI"),
-        uefi::cstr16!("\\grubx64.efi"),
-    ];
-    for path in boot_media_candidates.iter() {
-        let Ok(bytes) = fs.read(*path) else {
-            continue;
-        };
-        if is_probably_efi_image(bytes.as_slice()) {
-            return Ok(bytes);
-        }
-    }
-
-    Err(String::from("no se encontro GRUBX64.EFI para aplicar fallback de arranque"))
-}
-
 fn load_redux_payload_for_fallback(source_handle: uefi::Handle) -> Result<Vec<u8>, String> {
     let candidates = [
         uefi::cstr16!("\\EFI\\BOOT\\REDUX64.EFI"),
@@ -795,9 +807,11 @@ fn load_redux_payload_for_fallback(source_handle: uefi::Handle) -> Result<Vec<u8
     ))
 }
 
-fn build_forced_grub_config_payload(redux_path: &str, windows_path: Option<&str>) -> Vec<u8> {
+/// GRUB menu with Zenox first and then every system `osprober` found.
+pub(crate) fn build_forced_grub_config_payload(redux_path: &str, others: &[osprober::Target]) -> Vec<u8> {
     let mut cfg = alloc::format!(
-        "set timeout=8\r\n\
+        "{}\r\n\
+set timeout=8\r\n\
 set default=0\r\n\
 \r\n\
 menuentry \"Zenox OS\" {{\r\n\
@@ -810,45 +824,21 @@ menuentry \"Zenox OS\" {{\r\n\
         boot\r\n\
     fi\r\n\
 }}\r\n",
-        redux_path, redux_path
+        osprober::GENERATED_MARK, redux_path, redux_path
     );
 
-    if let Some(win_path) = windows_path {
-        cfg.push_str(
-            alloc::format!(
-                "\r\n\
-menuentry \"Windows 11\" {{\r\n\
-    if search --no-floppy --file --set=winroot {}; then\r\n\
-        chainloader ($winroot){}\r\n\
-        boot\r\n\
-    fi\r\n\
-}}\r\n",
-                win_path, win_path
-            )
-            .as_str(),
-        );
+    for target in others {
+        cfg.push_str("\r\n");
+        cfg.push_str(osprober::menuentry(target).as_str());
     }
+    cfg.push_str("\r\nmenuentry \"UEFI Firmware Settings\" {\r\n    fwsetup\r\n}\r\n");
 
     cfg.into_bytes()
 }
 
-fn write_forced_grub_config(
-    fs: &mut uefi::fs::FileSystem,
-    redux_path: &str,
-    windows_path: Option<&str>,
-) -> Result<(), String> {
-    let cfg = build_forced_grub_config_payload(redux_path, windows_path);
-
-    for dir in [
-        uefi::cstr16!("\\EFI\\GRUB"),
-        uefi::cstr16!("\\EFI\\BOOT"),
-        uefi::cstr16!("\\boot\\grub"),
-        uefi::cstr16!("\\BOOT\\GRUB"),
-    ] {
-        let _ = fs.create_dir_all(dir);
-    }
-
-    let cfg_candidates = [
+/// Where the generated GRUB config is written, for each way GRUB looks.
+pub(crate) fn grub_config_paths() -> [&'static uefi::CStr16; 8] {
+    [
         uefi::cstr16!("\\EFI\\GRUB\\GRUB.CFG"),
         uefi::cstr16!("\\EFI\\GRUB\\grub.cfg"),
         uefi::cstr16!("\\EFI\\BOOT\\GRUB.CFG"),
@@ -857,16 +847,10 @@ fn write_forced_grub_config(
         uefi::cstr16!("\\grub.cfg"),
         uefi::cstr16!("\\boot\\grub\\grub.cfg"),
         uefi::cstr16!("\\BOOT\\GRUB\\GRUB.CFG"),
-    ];
-
-    for path in cfg_candidates.iter() {
-        let _ = fs.write(*path, cfg.as_slice());
-    }
-
-    Ok(())
+    ]
 }
 
-fn collect_internal_simplefs_handles() -> Vec<uefi::Handle> {
+pub(crate) fn collect_internal_simplefs_handles() -> Vec<uefi::Handle> {
     use uefi::boot;
     use uefi::proto::media::fs::SimpleFileSystem;
 
@@ -1006,67 +990,6 @@ fn handle_has_any_path(handle: uefi::Handle, candidates: &[(&uefi::CStr16, &'sta
     false
 }
 
-fn detect_other_os_boot_target(
-    current_handle: Option<uefi::Handle>,
-    installed_redux: Option<uefi::Handle>,
-) -> Option<uefi::Handle> {
-    use uefi::boot;
-    use uefi::proto::media::fs::SimpleFileSystem;
-
-    let all_candidates: [(&uefi::CStr16, &'static str); 8] = [
-        (
-            uefi::cstr16!("\\EFI\\Microsoft\\Boot\\bootmgfw.redux.bak.efi"),
-            "\\EFI\\Microsoft\\Boot\\bootmgfw.redux.bak.efi",
-        ),
-        (uefi::cstr16!("\\EFI\\Microsoft\\Boot\\bootmgfw.efi"), "\\EFI\\Microsoft\\Boot\\bootmgfw.efi"),
-        (uefi::cstr16!("\\EFI\\MICROSOFT\\BOOT\\BOOTMGFW.EFI"), "\\EFI\\MICROSOFT\\BOOT\\BOOTMGFW.EFI"),
-        (uefi::cstr16!("\\EFI\\ubuntu\\shimx64.efi"), "\\EFI\\ubuntu\\shimx64.efi"),
-        (uefi::cstr16!("\\EFI\\ubuntu\\grubx64.efi"), "\\EFI\\ubuntu\\grubx64.efi"),
-        (uefi::cstr16!("\\EFI\\debian\\grubx64.efi"), "\\EFI\\debian\\grubx64.efi"),
-        (uefi::cstr16!("\\EFI\\fedora\\shimx64.efi"), "\\EFI\\fedora\\shimx64.efi"),
-        (uefi::cstr16!("\\EFI\\fedora\\grubx64.efi"), "\\EFI\\fedora\\grubx64.efi"),
-    ];
-    let current_handle_candidates: [(&uefi::CStr16, &'static str); 6] = [
-        (
-            uefi::cstr16!("\\EFI\\Microsoft\\Boot\\bootmgfw.redux.bak.efi"),
-            "\\EFI\\Microsoft\\Boot\\bootmgfw.redux.bak.efi",
-        ),
-        (uefi::cstr16!("\\EFI\\ubuntu\\shimx64.efi"), "\\EFI\\ubuntu\\shimx64.efi"),
-        (uefi::cstr16!("\\EFI\\ubuntu\\grubx64.efi"), "\\EFI\\ubuntu\\grubx64.efi"),
-        (uefi::cstr16!("\\EFI\\debian\\grubx64.efi"), "\\EFI\\debian\\grubx64.efi"),
-        (uefi::cstr16!("\\EFI\\fedora\\shimx64.efi"), "\\EFI\\fedora\\shimx64.efi"),
-        (uefi::cstr16!("\\EFI\\fedora\\grubx64.efi"), "\\EFI\\fedora\\grubx64.efi"),
-    ];
-
-    let handles = boot::find_handles::<SimpleFileSystem>().ok()?;
-    let mut fallback = None;
-
-    for handle in handles.iter().copied() {
-        if Some(handle) == installed_redux {
-            continue;
-        }
-        if handle_has_installed_redux_marker(handle) {
-            continue;
-        }
-        let candidates = if Some(handle) == current_handle {
-            current_handle_candidates.as_slice()
-        } else {
-            all_candidates.as_slice()
-        };
-        if !handle_has_any_path(handle, candidates) {
-            continue;
-        }
-        if handle_is_removable(handle) == Some(false) {
-            return Some(handle);
-        }
-        if fallback.is_none() {
-            fallback = Some(handle);
-        }
-    }
-
-    fallback
-}
-
 fn find_windows_boot_handle(
     current_handle: Option<uefi::Handle>,
     installed_redux: Option<uefi::Handle>,
@@ -1112,86 +1035,78 @@ fn start_image_from_handle<'a>(
     handle: uefi::Handle,
     image_candidates: &[(&uefi::CStr16, &'a str)],
 ) -> core::result::Result<&'a str, String> {
-    use uefi::boot::{self, LoadImageSource};
-    use uefi::proto::BootPolicy;
-    use uefi::proto::device_path::DevicePath;
-    use uefi::proto::device_path::build;
-
-    let parent_image = boot::image_handle();
     let mut last_error = String::from("no se encontro ejecutable EFI en destino");
 
-    'candidate: for (path_cstr, path_label) in image_candidates.iter() {
+    for (path_cstr, path_label) in image_candidates.iter() {
         if read_file_from_fs_handle(handle, *path_cstr).is_none() {
-            continue 'candidate;
+            continue;
+        }
+        match start_image_at(handle, path_cstr, None) {
+            Ok(()) => return Ok(*path_label),
+            Err(err) => last_error = err,
         }
+    }
 
-        let mut path_vec: Vec<u8> = Vec::new();
-        let full_path = {
-            let Ok(device_path_proto) = boot::open_protocol_exclusive::<DevicePath>(handle) else {
-                last_error = String::from("no se pudo abrir DevicePath del volumen destino");
-                continue 'candidate;
-            };
+    Err(last_error)
+}
 
-            let file_node = build::media::FilePath { path_name: *path_cstr };
-            let mut builder = build::DevicePathBuilder::with_vec(&mut path_vec);
-            for node in device_path_proto.node_iter() {
-                builder = match builder.push(&node) {
-                    Ok(next) => next,
-                    Err(_) => {
-                        last_error = String::from("fallo construyendo DevicePath");
-                        continue 'candidate;
-                    }
-                };
-            }
-            builder = match builder.push(&file_node) {
-                Ok(next) => next,
-                Err(_) => {
-                    last_error = String::from("fallo agregando archivo EFI al DevicePath");
-                    continue 'candidate;
-                }
-            };
-            match builder.finalize() {
-                Ok(path) => path,
-                Err(_) => {
-                    last_error = String::from("fallo finalizando DevicePath");
-                    continue 'candidate;
-                }
-            }
-        };
+/// Load and start the EFI image at `path` on the volume `handle`, passing it
+/// `options` as its command line. Returns when the image exits.
+pub(crate) fn start_image_at(
+    handle: uefi::Handle,
+    path: &uefi::CStr16,
+    options: Option<&uefi::CStr16>,
+) -> core::result::Result<(), String> {
+    use uefi::boot::{self, LoadImageSource};
+    use uefi::proto::device_path::build;
+    use uefi::proto::device_path::DevicePath;
+    use uefi::proto::loaded_image::LoadedImage;
+    use uefi::proto::BootPolicy;
 
-        let image_handle = match boot::load_image(
-            parent_image,
-            LoadImageSource::FromDevicePath {
-                device_path: full_path,
-                boot_policy: BootPolicy::ExactMatch,
-            },
-        ) {
-            Ok(h) => h,
-            Err(err) => {
-                last_error = alloc::format!("LoadImage fallo: {:?}", err);
-                continue;
-            }
-        };
+    let mut path_vec: Vec<u8> = Vec::new();
+    let full_path = {
+        let device_path_proto = boot::open_protocol_exclusive::<DevicePath>(handle)
+            .map_err(|_| String::from("no se pudo abrir DevicePath del volumen destino"))?;
 
-        match boot::start_image(image_handle) {
-            Ok(()) => return Ok(*path_label),
-            Err(err) => {
-                last_error = alloc::format!("StartImage fallo: {:?}", err);
-            }
+        let file_node = build::media::FilePath { path_name: path };
+        let mut builder = build::DevicePathBuilder::with_vec(&mut path_vec);
+        for node in device_path_proto.node_iter() {
+            builder = builder
+                .push(&node)
+                .map_err(|_| String::from("fallo construyendo DevicePath"))?;
         }
+        builder
+            .push(&file_node)
+            .map_err(|_| String::from("fallo agregando archivo EFI al DevicePath"))?
+            .finalize()
+            .map_err(|_| String::from("fallo finalizando DevicePath"))?
+    };
+
+    let image_handle = boot::load_image(
+        boot::image_handle(),
+        LoadImageSource::FromDevicePath {
+            device_path: full_path,
+            boot_policy: BootPolicy::ExactMatch,
+        },
+    )
+    .map_err(|err| alloc::format!("LoadImage fallo: {:?}", err))?;
+
+    if let Some(options) = options {
+        let mut loaded = boot::open_protocol_exclusive::<LoadedImage>(image_handle)
+            .map_err(|err| alloc::format!("LoadedImage no disponible: {:?}", err))?;
+        // `options` outlives StartImage, which is all the image may rely on.
+        unsafe { loaded.set_load_options(options.as_ptr().cast(), options.num_bytes() as u32) };
     }
 
-    Err(last_error)
+    boot::start_image(image_handle).map_err(|err| alloc::format!("StartImage fallo: {:?}", err))
 }
 
-fn launch_installed_redux(target_handle: Option<uefi::Handle>) -> core::result::Result<&'static str, String> {
+pub(crate) fn launch_installed_redux(target_handle: Option<uefi::Handle>) -> core::result::Result<&'static str, String> {
     let Some(handle) = target_handle else {
         return Err(String::from("no se detecto Zenox OS instalado en otro volumen"));
     };
 
-    let candidates: [(&uefi::CStr16, &'static str); 8] = [
-        (uefi::cstr16!("\\EFI\\GRUB\\GRUBX64.EFI"), "\\EFI\\GRUB\\GRUBX64.EFI"),
-        (uefi::cstr16!("\\EFI\\GRUB\\grubx64.efi"), "\\EFI\\GRUB\\grubx64.efi"),
+    let candidates: [(&uefi::CStr16, &'static str); 6] = [
         (uefi::cstr16!("\\EFI\\BOOT\\BOOTX64.EFI"), "\\EFI\\BOOT\\BOOTX64.EFI"),
         (uefi::cstr16!("\\EFI\\boot\\bootx64.efi"), "\\EFI\\boot\\bootx64.efi"),
         (uefi::cstr16!("\\EFI\\GOOS\\BOOTX64.EFI"), "\\EFI\\GOOS\\BOOTX64.EFI"),
@@ -1202,89 +1117,7 @@ fn launch_installed_redux(target_handle: Option<uefi::Handle>) -> core::result::
     start_image_from_handle(handle, candidates.as_slice())
 }
 
-fn launch_other_os_boot(
-    current_handle: Option<uefi::Handle>,
-    installed_redux: Option<uefi::Handle>,
-) -> core::result::Result<&'static str, String> {
-    use uefi::boot;
-    use uefi::proto::media::fs::SimpleFileSystem;
-
-    let all_candidates: [(&uefi::CStr16, &'static str); 8] = [
-        (
-            uefi::cstr16!("\\EFI\\Microsoft\\Boot\\bootmgfw.redux.bak.efi"),
-            "\\EFI\\Microsoft\\Boot\\bootmgfw.redux.bak.efi",
-        ),
-        (uefi::cstr16!("\\EFI\\Microsoft\\Boot\\bootmgfw.efi"), "\\EFI\\Microsoft\\Boot\\bootmgfw.efi"),
-        (uefi::cstr16!("\\EFI\\MICROSOFT\\BOOT\\BOOTMGFW.EFI"), "\\EFI\\MICROSOFT\\BOOT\\BOOTMGFW.EFI"),
-        (uefi::cstr16!("\\EFI\\ubuntu\\shimx64.efi"), "\\EFI\\ubuntu\\shimx64.efi"),
-        (uefi::cstr16!("\\EFI\\ubuntu\\grubx64.efi"), "\\EFI\\ubuntu\\grubx64.efi"),
-        (uefi::cstr16!("\\EFI\\debian\\grubx64.efi"), "\\EFI\\debian\\grubx64.efi"),
-        (uefi::cstr16!("\\EFI\\fedora\\shimx64.efi"), "\\EFI\\fedora\\shimx64.efi"),
-        (uefi::cstr16!("\\EFI\\fedora\\grubx64.efi"), "\\EFI\\fedora\\grubx64.efi"),
-    ];
-    let current_handle_candidates: [(&uefi::CStr16, &'static str); 6] = [
-        (
-            uefi::cstr16!("\\EFI\\Microsoft\\Boot\\bootmgfw.redux.bak.efi"),
-            "\\EFI\\Microsoft\\Boot\\bootmgfw.redux.bak.efi",
-        ),
-        (uefi::cstr16!("\\EFI\\ubuntu\\shimx64.efi"), "\\EFI\\ubuntu\\shimx64.efi"),
-        (uefi::cstr16!("\\EFI\\ubuntu\\grubx64.efi"), "\\EFI\\ubuntu\\grubx64.efi"),
-        (uefi::cstr16!("\\EFI\\debian\\grubx64.efi"), "\\EFI\\debian\\grubx64.efi"),
-        (uefi::cstr16!("\\EFI\\fedora\\shimx64.efi"), "\\EFI\\fedora\\shimx64.efi"),
-        (uefi::cstr16!("\\EFI\\fedora\\grubx64.efi"), "\\EFI\\fedora\\grubx64.efi"),
-    ];
-
-    let handles = boot::find_handles::<SimpleFileSystem>()
-        .map_err(|err| alloc::format!("no hay volúmenes SimpleFS: {:?}", err))?;
-    let mut last_error = String::from("no se encontro cargador EFI de otro sistema");
-
-    // Prefer internal volumes first, then removable.
-    for pass in 0..2 {
-        for handle in handles.iter().copied() {
-            if Some(handle) == installed_redux {
-                continue;
-            }
-            if handle_has_installed_redux_marker(handle) {
-                continue;
-            }
-            let removable = handle_is_removable(handle).unwrap_or(false);
-            if (pass == 0 && removable) || (pass == 1 && !removable) {
-                continue;
-            }
-
-            let candidates = if Some(handle) == current_handle {
-                current_handle_candidates.as_slice()
-            } else {
-                all_candidates.as_slice()
-            };
-
-            match start_image_from_handle(handle, candidates) {
-                Ok(path) => return Ok(path),
-                Err(err) => last_error = err,
-            }
-        }
-    }
-
-    if let Some(win_id) = find_windows_boot_option_id()? {
-        if let Some(redux_id) = find_redux_boot_option_id()? {
-            let _ = ensure_boot_order_contains(redux_id);
-        }
-        write_boot_next(win_id)?;
-        println(
-            alloc::format!(
-                "Otro SO fallback: BootNext=Boot{:04X} (Windows). Reiniciando...",
-                win_id
-            )
-            .as_str(),
-        );
-        uefi::boot::stall(300_000);
-        uefi::runtime::reset(ResetType::COLD, Status::SUCCESS, None);
-    }
-
-    Err(last_error)
-}
-
-fn detect_linux_guest_boot_target(
+pub(crate) fn detect_linux_guest_boot_target(
     current_handle: Option<uefi::Handle>,
     installed_redux: Option<uefi::Handle>,
 ) -> Option<uefi::Handle> {
@@ -1316,10 +1149,9 @@ fn detect_linux_guest_boot_target(
         if handle_has_installed_redux_marker(handle) {
             continue;
         }
-        if Some(handle) == current_handle && !handle_has_any_path(handle, candidates.as_slice()) {
-            continue;
-        }
-        if !handle_has_any_path(handle, candidates.as_slice()) {
+        if !handle_has_any_path(handle, candidates.as_slice())
+            && linuxboot::guest_kernels(handle, "").is_empty()
+        {
             continue;
         }
         if handle_is_removable(handle) == Some(false) {
@@ -1332,10 +1164,10 @@ fn detect_linux_guest_boot_target(
     fallback
 }
 
-fn launch_linux_guest_boot(
+pub(crate) fn launch_linux_guest_boot(
     current_handle: Option<uefi::Handle>,
     installed_redux: Option<uefi::Handle>,
-) -> core::result::Result<&'static str, String> {
+) -> core::result::Result<String, String> {
     use uefi::boot;
     use uefi::proto::media::fs::SimpleFileSystem;
 
@@ -1377,12 +1209,35 @@ fn launch_linux_guest_boot(
                 all_candidates.as_slice()
             };
             match start_image_from_handle(handle, candidates) {
-                Ok(path) => return Ok(path),
+                Ok(path) => return Ok(String::from(path)),
                 Err(err) => last_error = err,
             }
         }
     }
 
+    // No EFI stub image: a plain vmlinuz in the guest directories.
+    let options = linuxboot::command_line();
+    for pass in 0..2 {
+        for handle in handles.iter().copied() {
+            if Some(handle) == installed_redux || handle_has_installed_redux_marker(handle) {
+                continue;
+            }
+            let removable = handle_is_removable(handle).unwrap_or(false);
+            if (pass == 0 && removable) || (pass == 1 && !removable) {
+                continue;
+            }
+            for target in linuxboot::guest_kernels(handle, options.as_str()) {
+                let osprober::Boot::Linux { kernel, initrds, options } = &target.boot else {
+                    continue;
+                };
+                match linuxboot::boot(handle, kernel.as_str(), initrds.as_slice(), options.as_str()) {
+                    Ok(()) => return Ok(kernel.clone()),
+                    Err(err) => last_error = err,
+                }
+            }
+        }
+    }
+
     Err(last_error)
 }
 
@@ -1392,13 +1247,13 @@ pub(crate) fn linux_guest_efi_available() -> bool {
     detect_linux_guest_boot_target(current_handle, installed_handle).is_some()
 }
 
-pub(crate) fn launch_linux_guest_uefi() -> core::result::Result<&'static str, String> {
+pub(crate) fn launch_linux_guest_uefi() -> core::result::Result<String, String> {
     let current_handle = current_boot_device_handle();
     let installed_handle = find_installed_redux_handle(current_handle);
     launch_linux_guest_boot(current_handle, installed_handle)
 }
 
-fn extract_boot_option_description(data: &[u8]) -> Option<String> {
+pub(crate) fn extract_boot_option_description(data: &[u8]) -> Option<String> {
     if data.len() < 8 {
         return None;
     }
@@ -1438,7 +1293,7 @@ fn contains_ascii_utf16_case_insensitive(data: &[u8], needle_lower: &str) -> boo
     text.contains(needle_lower)
 }
 
-fn read_boot_option_variable(id: u16) -> Result<Option<Vec<u8>>, String> {
+pub(crate) fn read_boot_option_variable(id: u16) -> Result<Option<Vec<u8>>, String> {
     let vendor = uefi::runtime::VariableVendor::GLOBAL_VARIABLE;
     let name = CString16::try_from(alloc::format!("Boot{:04X}", id).as_str())
         .map_err(|_| String::from("nombre Boot#### invalido"))?;
@@ -1566,7 +1421,7 @@ fn boot_option_is_legacy_redux_family(raw: &[u8], desc_lower: &str) -> bool {
         || contains_ascii_utf16_case_insensitive(raw, "\\efi\\reduxos\\")
 }
 
-fn extract_boot_option_optional_data(data: &[u8]) -> Option<&[u8]> {
+pub(crate) fn extract_boot_option_optional_data(data: &[u8]) -> Option<&[u8]> {
     let path = extract_boot_option_file_path(data)?;
     let path_start = path.as_ptr() as usize - data.as_ptr() as usize;
     let optional_start = path_start.checked_add(path.len())?;
@@ -1615,6 +1470,9 @@ fn maybe_rename_legacy_redux_boot_options() {
 }
 
 fn maybe_ensure_redux_boot_priority() {
+    if bootvar::user_managed() {
+        return;
+    }
     let Ok(Some(redux_id)) = find_redux_boot_option_id() else {
         return;
     };
@@ -1627,15 +1485,14 @@ fn ensure_installed_boot_option_registered() -> Result<String, String> {
         Some(handle) => handle,
         None => {
             reconnect_storage_controllers();
-            uefi::boot::stall(150_000);
-            find_installed_redux_handle(current_handle)
-                .ok_or_else(|| String::from("no se detecto instalacion interna de Zenox OS"))?
+            boottime::wait_for(STORAGE_RECONNECT_TIMEOUT_US, STORAGE_RECONNECT_POLL_US, || {
+                find_installed_redux_handle(current_handle)
+            })
+            .ok_or_else(|| String::from("no se detecto instalacion interna de Zenox OS"))?
         }
     };
 
-    let path_candidates: [(&uefi::CStr16, &'static str); 8] = [
-        (uefi::cstr16!("\\EFI\\GRUB\\GRUBX64.EFI"), "\\EFI\\GRUB\\GRUBX64.EFI"),
-        (uefi::cstr16!("\\EFI\\GRUB\\grubx64.efi"), "\\EFI\\GRUB\\grubx64.efi"),
+    let path_candidates: [(&uefi::CStr16, &'static str); 6] = [
         (uefi::cstr16!("\\EFI\\BOOT\\BOOTX64.EFI"), "\\EFI\\BOOT\\BOOTX64.EFI"),
         (uefi::cstr16!("\\EFI\\boot\\bootx64.efi"), "\\EFI\\boot\\bootx64.efi"),
         (uefi::cstr16!("\\EFI\\GOOS\\BOOTX64.EFI"), "\\EFI\\GOOS\\BOOTX64.EFI"),
@@ -1648,9 +1505,10 @@ fn ensure_installed_boot_option_registered() -> Result<String, String> {
         Some(path) => path,
         None => {
             reconnect_storage_controllers();
-            uefi::boot::stall(150_000);
-            select_boot_path_candidate(target_handle, path_candidates.as_slice())
-                .ok_or_else(|| String::from("no se encontro BOOTX64.EFI en la instalacion interna"))?
+            boottime::wait_for(STORAGE_RECONNECT_TIMEOUT_US, STORAGE_RECONNECT_POLL_US, || {
+                select_boot_path_candidate(target_handle, path_candidates.as_slice())
+            })
+            .ok_or_else(|| String::from("no se encontro BOOTX64.EFI en la instalacion interna"))?
         }
     };
 
@@ -1672,6 +1530,10 @@ fn ensure_installed_boot_option_registered() -> Result<String, String> {
     write_boot_option_variable(free_id, load_option.as_slice())?;
     ensure_boot_order_contains(free_id)?;
     let _ = write_boot_next(free_id);
+    // Reinstalls leave old entries behind; point at them, never delete here.
+    if let Some(hint) = bootvar::stale_hint() {
+        println(hint.as_str());
+    }
 
     Ok(alloc::format!(
         "Entrada UEFI creada: Boot{:04X} ({})",
@@ -1711,7 +1573,7 @@ fn build_file_device_path_bytes(
     Ok(full_path.as_bytes().to_vec())
 }
 
-fn build_boot_load_option(
+pub(crate) fn build_boot_load_option(
     description: &str,
     file_path_list: &[u8],
     optional_data: &[u8],
@@ -1732,14 +1594,14 @@ fn build_boot_load_option(
     Ok(data)
 }
 
-fn parse_boot_option_id_from_name(name: &str) -> Option<u16> {
+pub(crate) fn parse_boot_option_id_from_name(name: &str) -> Option<u16> {
     if name.len() != 8 || !name.starts_with("Boot") {
         return None;
     }
     u16::from_str_radix(&name[4..], 16).ok()
 }
 
-fn extract_boot_option_file_path(data: &[u8]) -> Option<&[u8]> {
+pub(crate) fn extract_boot_option_file_path(data: &[u8]) -> Option<&[u8]> {
     if data.len() < 6 {
         return None;
     }
@@ -1812,7 +1674,7 @@ fn find_free_boot_option_id() -> Result<u16, String> {
     Err(String::from("sin identificador libre para Boot####"))
 }
 
-fn read_boot_order() -> Result<Vec<u16>, String> {
+pub(crate) fn read_boot_order() -> Result<Vec<u16>, String> {
     let vendor = uefi::runtime::VariableVendor::GLOBAL_VARIABLE;
     let (raw, _attrs) = match uefi::runtime::get_variable_boxed(uefi::cstr16!("BootOrder"), &vendor) {
         Ok(v) => v,
@@ -1833,7 +1695,7 @@ fn read_boot_order() -> Result<Vec<u16>, String> {
     Ok(order)
 }
 
-fn write_boot_order(order: &[u16]) -> Result<(), String> {
+pub(crate) fn write_boot_order(order: &[u16]) -> Result<(), String> {
     let vendor = uefi::runtime::VariableVendor::GLOBAL_VARIABLE;
     let attrs = uefi::runtime::VariableAttributes::NON_VOLATILE
         | uefi::runtime::VariableAttributes::BOOTSERVICE_ACCESS
@@ -1855,7 +1717,7 @@ fn ensure_boot_order_contains(id: u16) -> Result<(), String> {
     write_boot_order(order.as_slice())
 }
 
-fn write_boot_next(id: u16) -> Result<(), String> {
+pub(crate) fn write_boot_next(id: u16) -> Result<(), String> {
     let vendor = uefi::runtime::VariableVendor::GLOBAL_VARIABLE;
     let attrs = uefi::runtime::VariableAttributes::NON_VOLATILE
         | uefi::runtime::VariableAttributes::BOOTSERVICE_ACCESS
@@ -1865,7 +1727,7 @@ fn write_boot_next(id: u16) -> Result<(), String> {
         .map_err(|err| alloc::format!("escribiendo BootNext: {:?}", err.status()))
 }
 
-fn write_boot_option_variable(id: u16, data: &[u8]) -> Result<(), String> {
+pub(crate) fn write_boot_option_variable(id: u16, data: &[u8]) -> Result<(), String> {
     let vendor = uefi::runtime::VariableVendor::GLOBAL_VARIABLE;
     let attrs = uefi::runtime::VariableAttributes::NON_VOLATILE
         | uefi::runtime::VariableAttributes::BOOTSERVICE_ACCESS
@@ -1879,12 +1741,12 @@ fn write_boot_option_variable(id: u16, data: &[u8]) -> Result<(), String> {
 
 fn reset_global_fat_mount_state() {
     unsafe {
-        crate::fat32::GLOBAL_FAT.unmount();
+        crate::fat32::GLOBAL_FAT.get_mut().unmount();
     }
 }
 
 fn shell_loop(mut current_cluster: u32) -> ! {
-    let fs_state = unsafe { &mut crate::fat32::GLOBAL_FAT };
+    let fs_state = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
     let mut line = [0u8; LINE_MAX];
     let mut len = 0usize;
 
@@ -1894,6 +1756,8 @@ fn shell_loop(mut current_cluster: u32) -> ! {
     loop {
         let tick = timer::on_tick();
         scheduler::on_tick(tick);
+        timer::run_due();
+        executor::run_ready();
 
         if let Some(event) = poll_input_event() {
             match event {
@@ -1924,6 +1788,7 @@ fn shell_loop(mut current_cluster: u32) -> ! {
                     println("Use command 'reboot' to restart.");
                     prompt();
                 }
+                InputEvent::Up | InputEvent::Down => {}
             }
         }
 
@@ -1935,53 +1800,273 @@ fn handle_command(cmd: &str, fat: &mut crate::fat32::Fat32, current_cluster: &mu
     if cmd.is_empty() {
         return;
     }
+    let _acting = perm::act_as(perm::session());
+
+    if recovery::active() {
+        if !recovery::allowed(cmd) {
+            println("No disponible en modo de recuperacion; 'help' lista lo que hay, 'continuar' sigue al escritorio.");
+            return;
+        }
+        if cmd == "help" {
+            for line in recovery::help_lines().iter() {
+                println(line.as_str());
+            }
+            return;
+        }
+        if cmd == "continuar" {
+            recovery::leave();
+            net::init();
+            start_gui_mode();
+        }
+    }
 
     if handle_fs_command(cmd, fat, current_cluster) {
         return;
     }
 
     if cmd == "help" {
-        println("Commands:");
-        println("  help           - show this help");
-        println("  about          - system info");
-        println("  clear          - clear screen");
-        println("  mem            - memory map stats");
-        println("  alloc          - allocate one 4KiB frame");
-        println("  idt            - IDT skeleton info");
-        println("  tick           - timer/uptime info");
-        println("  sched          - scheduler stats");
-        println("  acpi           - ACPI S3 suspend diagnostics");
-        println("  suspend        - try ACPI S3 suspend");
-        println("  step           - run +100 virtual ticks");
-        println("  format         - format virtual disk as FAT32");
-        println("  boot           - exit boot services and start IRQ-safe runtime (auto fallback)");
-        println("  boot poll      - force stable polling runtime");
-        println("  boot uefi      - start GUI without ExitBootServices (UEFI input: USB OK)");
-        println("  boot irq       - start experimental PIT/IRQ runtime (auto fallback)");
-        println("                   runtime shell runs in user-space via syscalls");
-        println("  echo <text>    - print text");
-        println("  panic          - panic test");
-        println("  reboot         - reboot VM");
-        println("  gui            - enter windowed desktop mode");
-        println("  installer      - open graphical pre-boot installer");
-        println("  disks          - list UEFI BlockIO devices (USB/NVMe/HDD)");
-        println("  vols           - list mountable FAT32/exFAT volumes");
-        println("  mount <n>      - mount FAT32/exFAT from BlockIO device index in 'disks'");
-        println("  cppdoom        - launch CPP-DOOM native GUI app");
-        println("  shell          - chainload external UEFI Shell image (SHELLX64.EFI)");
-        println("  linux guest    - chainload Linux guest EFI loader (ruta 2: compat Linux real)");
-        println("  net            - show network transport/IP status");
-        println("  net dhcp       - switch to DHCP mode and request dynamic IP");
-        println("  net static     - apply default static IP profile");
-        println("  net static <ip> <prefijo> <gateway> - apply custom static IP");
-        println("  net mode       - show current IP mode");
-        println("  net https <on|off|status> - HTTPS compatibility mode");
-        println("  net diag       - dump Intel Ethernet RX/TX registers");
-        println("  wifi           - show Intel WiFi native driver status");
-        println("  wifi scan      - scan WiFi networks (phase2 for real RF scan)");
-        println("  wifi connect <ssid> <clave> - save profile and connect");
-        println("  wifi disconnect - disconnect WiFi");
-        println("  wifi failover <ethernet|wifi|status> - set automatic priority");
+        for line in i18n::shell_help_lines().iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "dmesg" || cmd == "log" || cmd.starts_with("log ") {
+        let args = cmd.strip_prefix("log").unwrap_or("");
+        let args = if cmd == "dmesg" { "" } else { args };
+        // Bypass the ring so dumping the log does not duplicate it.
+        for line in klog::command_lines(args).iter() {
+            println_unlogged(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "lspci" || cmd.starts_with("lspci ") {
+        for line in pci::command_lines(cmd.strip_prefix("lspci").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "hwinfo" || cmd.starts_with("hwinfo ") {
+        for line in hwinfo::command_lines(cmd.strip_prefix("hwinfo").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if let Some(args) = bench::command_args(cmd) {
+        for line in bench::command_lines(args.as_str(), None).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "cryptobench" || cmd.starts_with("cryptobench ") {
+        for line in crypto::command_lines(cmd.strip_prefix("cryptobench").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "osprober" || cmd.starts_with("osprober ") {
+        for line in osprober::command_lines(cmd.strip_prefix("osprober").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "parts" {
+        for line in preboot_installer::partition_table_lines().iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "uninstall" || cmd.starts_with("uninstall ") {
+        for line in recovery::uninstall_lines(cmd.strip_prefix("uninstall").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "bootvar" || cmd.starts_with("bootvar ") {
+        for line in bootvar::command_lines(cmd.strip_prefix("bootvar").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "crypt" || cmd.starts_with("crypt ") {
+        for line in crypt::command_lines(cmd.strip_prefix("crypt").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "tpm" || cmd.starts_with("tpm ") {
+        for line in tpm::command_lines(cmd.strip_prefix("tpm").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "secureboot" || cmd.starts_with("secureboot ") {
+        for line in secureboot::command_lines(cmd.strip_prefix("secureboot").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "random" || cmd.starts_with("random ") {
+        for line in random::command_lines(cmd.strip_prefix("random").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "selftest" || cmd.starts_with("selftest ") {
+        for line in selftest::command_lines(cmd.strip_prefix("selftest").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "firewall" || cmd.starts_with("firewall ") {
+        for line in crate::net::firewall::command_lines(cmd.strip_prefix("firewall").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "gemini" || cmd.starts_with("gemini ") {
+        for line in crate::net::gemini::command_lines(cmd.strip_prefix("gemini").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "ftp" || cmd.starts_with("ftp ") {
+        let dir_cluster = if *current_cluster == 0 { fat.root_cluster } else { *current_cluster };
+        for line in crate::net::ftp::command_lines(cmd.strip_prefix("ftp").unwrap_or(""), dir_cluster, &mut || {}).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "mail" || cmd.starts_with("mail ") {
+        let dir_cluster = if *current_cluster == 0 { fat.root_cluster } else { *current_cluster };
+        for line in crate::net::mail::command_lines(cmd.strip_prefix("mail").unwrap_or(""), dir_cluster, &mut || {}).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "gzip" || cmd.starts_with("gzip ") || cmd == "gunzip" || cmd.starts_with("gunzip ") {
+        let dir_cluster = if *current_cluster == 0 { fat.root_cluster } else { *current_cluster };
+        let (verb, args) = cmd.split_once(' ').unwrap_or((cmd, ""));
+        for line in compress::command_lines(verb, args, dir_cluster).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if ["tar", "unzip", "zip"].iter().any(|v| cmd == *v || cmd.starts_with(alloc::format!("{} ", v).as_str())) {
+        let dir_cluster = if *current_cluster == 0 { fat.root_cluster } else { *current_cluster };
+        let (verb, args) = cmd.split_once(' ').unwrap_or((cmd, ""));
+        for line in archive::command_lines(verb, args, dir_cluster).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "notify" || cmd.starts_with("notify ") {
+        for line in gui::notifications::command_lines(cmd.strip_prefix("notify").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "config" || cmd.starts_with("config ") {
+        for line in config::command_lines(cmd.strip_prefix("config").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "gamepad test" {
+        gamepad_test_live();
+        return;
+    }
+
+    if cmd == "gamepad" || cmd.starts_with("gamepad ") {
+        for line in gamepad::command_lines(cmd.strip_prefix("gamepad").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "perf" || cmd.starts_with("perf ") {
+        for line in perf::command_lines(cmd.strip_prefix("perf").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "boottime" {
+        for line in boottime::command_lines("").iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "trace" || cmd.starts_with("trace ") {
+        for line in trace::command_lines(cmd.strip_prefix("trace").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "scale" || cmd.starts_with("scale ") {
+        for line in gui::hidpi::command_lines(cmd.strip_prefix("scale").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "effects" || cmd.starts_with("effects ") {
+        for line in gui::effects::command_lines(cmd.strip_prefix("effects").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "session" || cmd.starts_with("session ") {
+        for line in gui::session::command_lines(cmd.strip_prefix("session").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "lang" || cmd.starts_with("lang ") {
+        for line in i18n::command_lines(cmd.strip_prefix("lang").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "pkg" || cmd.starts_with("pkg ") {
+        for line in pkg::command_lines(cmd.strip_prefix("pkg").unwrap_or(""), &mut || {}).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "host" || cmd.starts_with("host ") {
+        let dir_cluster = if *current_cluster == 0 { fat.root_cluster } else { *current_cluster };
+        for line in hostfs::command_lines(cmd.strip_prefix("host").unwrap_or(""), dir_cluster).iter() {
+            println(line.as_str());
+        }
         return;
     }
 
@@ -1990,18 +2075,59 @@ fn handle_command(cmd: &str, fat: &mut crate::fat32::Fat32, current_cluster: &mu
         return;
     }
 
-    if cmd == "suspend" || cmd == "sleep" {
-        println("Attempting ACPI S3 suspend...");
-        match crate::acpi::try_suspend_to_ram() {
-            Ok(()) => println("ACPI S3 returned from wake."),
+    if cmd == "suspend" || cmd == "sleep" || cmd.starts_with("suspend ") {
+        let mode = match cmd.strip_prefix("suspend ") {
+            Some(arg) => match crate::pm::SleepMode::parse(arg) {
+                Some(mode) => mode,
+                None => {
+                    println("Uso: suspend [auto|s3|s2idle]");
+                    return;
+                }
+            },
+            None => crate::pm::sleep_mode(),
+        };
+        println(alloc::format!("Attempting {} suspend...", mode.name()).as_str());
+        match crate::pm::sleep(mode) {
+            Ok(crate::pm::Slept::Resumed) => println("ACPI S3 returned from wake."),
+            Ok(crate::pm::Slept::Idle) => {
+                println("s2idle: press a key, open the lid or press power to wake.");
+                loop {
+                    if poll_input_event().is_some() {
+                        break;
+                    }
+                    if matches!(
+                        crate::pm::poll_events(),
+                        Some(crate::pm::PowerEvent::LidOpened | crate::pm::PowerEvent::PowerButton)
+                    ) {
+                        break;
+                    }
+                    uefi::boot::stall(LOOP_STALL_US);
+                }
+                crate::pm::resume_devices();
+                println("s2idle: awake.");
+            }
             Err(err) => {
-                println("ACPI S3 failed:");
-                println(err);
+                println("Suspend failed:");
+                println(err.as_str());
             }
         }
         return;
     }
 
+    if cmd == "cmdline" {
+        for line in crate::cmdline::command_lines().iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "power" || cmd.starts_with("power ") {
+        for line in crate::pm::command_lines(cmd.strip_prefix("power").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
     if cmd == "installer" {
         let result = preboot_installer::run();
         println("Kernel stage: installer returned.");
@@ -2023,7 +2149,7 @@ fn handle_command(cmd: &str, fat: &mut crate::fat32::Fat32, current_cluster: &mu
             preboot_installer::InstallerResult::Skipped => {
                 println("Preboot installer: skipped.");
                 println("Kernel stage: auto-launch GUI mode after installer.");
-                uefi::boot::stall(300_000);
+                settle_keys_before_gui();
                 start_gui_mode();
             }
         }
@@ -2033,17 +2159,198 @@ fn handle_command(cmd: &str, fat: &mut crate::fat32::Fat32, current_cluster: &mu
     if cmd == "linux guest" || cmd == "lguest" {
         let current_handle = current_boot_device_handle();
         let installed_handle = find_installed_redux_handle(current_handle);
-        println("Arranque: Linux guest...");
+        println(i18n::tr("boot.booting_linux").as_str());
         match launch_linux_guest_boot(current_handle, installed_handle) {
-            Ok(path) => println(alloc::format!("Arranque regresó desde {}.", path).as_str()),
+            Ok(path) => println(i18n::trf("boot.returned", &[&path]).as_str()),
             Err(err) => {
-                println(alloc::format!("No se pudo arrancar Linux guest: {}", err).as_str());
+                println(i18n::trf("boot.failed_linux", &[&err]).as_str());
                 println("Rutas buscadas: \\EFI\\LINUX\\BOOTX64.EFI, \\EFI\\BOOT\\LINUX.EFI, \\boot\\vmlinuz.efi");
+                println("Nucleos planos: vmlinuz[-version] + initrd.img en \\EFI\\LINUX o \\LINUX");
             }
         }
         return;
     }
 
+    if cmd == "async" || cmd.starts_with("async ") {
+        for line in executor::command_lines(cmd.strip_prefix("async").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "jobs" || cmd.starts_with("jobs ") {
+        for line in procenv::command_lines(cmd.strip_prefix("jobs").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "touch" || cmd.starts_with("touch ") {
+        for line in touch::command_lines(cmd.strip_prefix("touch").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "gamma" || cmd.starts_with("gamma ") {
+        for line in gamma::command_lines(cmd.strip_prefix("gamma").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "print" || cmd.starts_with("print ") {
+        for line in print::command_lines(cmd.strip_prefix("print").unwrap_or(""), &mut || {}).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "pdf" || cmd.starts_with("pdf ") {
+        let dir_cluster = if *current_cluster == 0 { fat.root_cluster } else { *current_cluster };
+        for line in pdf::command_lines(cmd.strip_prefix("pdf").unwrap_or(""), dir_cluster).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "calc" || cmd.starts_with("calc ") {
+        for line in calc::command_lines(cmd.strip_prefix("calc").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "cal" || cmd.starts_with("cal ") {
+        for line in calendar::command_lines(cmd.strip_prefix("cal").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "alarm" || cmd.starts_with("alarm ") {
+        for line in alarm::command_lines(cmd.strip_prefix("alarm").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "mic" || cmd.starts_with("mic ") {
+        for line in capture::command_lines(cmd.strip_prefix("mic").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "sound" || cmd.starts_with("sound ") {
+        let dir_cluster = if *current_cluster == 0 { fat.root_cluster } else { *current_cluster };
+        for line in synth::command_lines(cmd.strip_prefix("sound").unwrap_or(""), dir_cluster).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "remote" || cmd.starts_with("remote ") {
+        for line in net::remote::command_lines(cmd.strip_prefix("remote").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "usb" || cmd.starts_with("usb ") {
+        for line in usb::command_lines(cmd.strip_prefix("usb").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "thunderbolt" || cmd.starts_with("thunderbolt ") {
+        for line in thunderbolt::command_lines(cmd.strip_prefix("thunderbolt").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "index" || cmd.starts_with("index ") {
+        for line in search_index::command_lines(cmd.strip_prefix("index").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "heapcheck" || cmd.starts_with("heapcheck ") {
+        for line in heapcheck::command_lines(cmd.strip_prefix("heapcheck").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "fswatch" || cmd.starts_with("fswatch ") {
+        for line in fswatch::command_lines(cmd.strip_prefix("fswatch").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    for verb in ["su", "id", "whoami"] {
+        let Some(args) = cmd.strip_prefix(verb).filter(|rest| rest.is_empty() || rest.starts_with(' ')) else {
+            continue;
+        };
+        for line in perm::session_lines(verb, args).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "osk" || cmd.starts_with("osk ") {
+        for line in gui::osk::command_lines(cmd.strip_prefix("osk").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "clocksource" || cmd.starts_with("clocksource ") {
+        for line in clocksource::command_lines(cmd.strip_prefix("clocksource").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "devices" || cmd.starts_with("devices ") {
+        for line in device::command_lines(cmd.strip_prefix("devices").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "mod" || cmd.starts_with("mod ") {
+        for line in kmod::command_lines(cmd.strip_prefix("mod").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "checkpoint" || cmd.starts_with("checkpoint ") {
+        for line in checkpoint::command_lines(cmd.strip_prefix("checkpoint").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "replay" || cmd.starts_with("replay ") {
+        for line in replay::command_lines(cmd.strip_prefix("replay").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
+    if cmd == "linuxboot" || cmd.starts_with("linuxboot ") {
+        for line in linuxboot::command_lines(cmd.strip_prefix("linuxboot").unwrap_or("")).iter() {
+            println(line.as_str());
+        }
+        return;
+    }
+
     if cmd == "gui" {
         start_gui_mode();
     }
@@ -2226,6 +2533,70 @@ fn handle_command(cmd: &str, fat: &mut crate::fat32::Fat32, current_cluster: &mu
                 return;
             }
 
+            if sub.eq_ignore_ascii_case("bench") {
+                let rest: Vec<&str> = parts.collect();
+                for line in crate::net::bench::command_lines(rest.join(" ").as_str(), &mut || {}).iter() {
+                    println(line.as_str());
+                }
+                return;
+            }
+
+            if sub.eq_ignore_ascii_case("pool") {
+                let rest: Vec<&str> = parts.collect();
+                for line in crate::net::pool::command_lines(rest.join(" ").as_str()).iter() {
+                    println(line.as_str());
+                }
+                return;
+            }
+
+            if sub.eq_ignore_ascii_case("profile") {
+                let rest: Vec<&str> = parts.collect();
+                for line in crate::net::profile::command_lines(rest.join(" ").as_str()).iter() {
+                    println(line.as_str());
+                }
+                return;
+            }
+
+            if sub.eq_ignore_ascii_case("portal") {
+                let rest: Vec<&str> = parts.collect();
+                for line in crate::net::portal::command_lines(rest.join(" ").as_str()).iter() {
+                    println(line.as_str());
+                }
+                return;
+            }
+
+            if sub.eq_ignore_ascii_case("stats") {
+                let rest: Vec<&str> = parts.collect();
+                for line in crate::net::stats::command_lines(rest.join(" ").as_str()).iter() {
+                    println(line.as_str());
+                }
+                return;
+            }
+
+            if sub.eq_ignore_ascii_case("arp") {
+                let rest: Vec<&str> = parts.collect();
+                for line in crate::net::arp::command_lines(rest.join(" ").as_str()).iter() {
+                    println(line.as_str());
+                }
+                return;
+            }
+
+            if sub.eq_ignore_ascii_case("mtu") {
+                let rest: Vec<&str> = parts.collect();
+                for line in crate::net::mtu::command_lines(rest.join(" ").as_str()).iter() {
+                    println(line.as_str());
+                }
+                return;
+            }
+
+            if sub.eq_ignore_ascii_case("jobs") {
+                let rest: Vec<&str> = parts.collect();
+                for line in crate::net::worker::command_lines(rest.join(" ").as_str()).iter() {
+                    println(line.as_str());
+                }
+                return;
+            }
+
             if sub.eq_ignore_ascii_case("diag") {
                 if let Some(diag) = crate::intel_net::get_diagnostics() {
                     let rxq_en = (diag.rxdctl & 0x0200_0000) != 0;
@@ -2282,22 +2653,32 @@ fn handle_command(cmd: &str, fat: &mut crate::fat32::Fat32, current_cluster: &mu
                     );
                 } else {
                     println("NetDiag: Intel Ethernet no inicializado.");
+                    if crate::virtio::net::is_present() {
+                        println(crate::virtio::net::status_line().as_str());
+                    }
                 }
                 return;
             }
 
-            println("Usage: net [dhcp|static|static <ip> <prefijo> <gateway>|mode|https|diag]");
+            println("Usage: net [dhcp|static|static <ip> <prefijo> <gateway>|mode|https|diag|bench|pool|jobs|profile|portal|stats|arp|mtu]");
             return;
         }
 
         let active = crate::net::get_active_transport();
-        let dhcp_status = unsafe { crate::net::DHCP_STATUS };
+        let dhcp_status = crate::net::dhcp_status();
         let (s_ip, s_prefix, s_gw) = crate::net::get_static_ipv4_config();
         println(alloc::format!("Net: Transporte activo -> {}", active).as_str());
         println(alloc::format!("Net: Failover policy -> {}", crate::net::get_failover_policy()).as_str());
         println(alloc::format!("Net: Modo IP -> {}", crate::net::get_network_mode()).as_str());
         println(alloc::format!("Net: HTTPS mode -> {}", crate::net::get_https_mode()).as_str());
         println(alloc::format!("Net: Estado IP -> {}", dhcp_status).as_str());
+        for line in crate::net::dhcp::status_lines() {
+            println(line.as_str());
+        }
+        for line in crate::net::profile::status_lines() {
+            println(line.as_str());
+        }
+        println(alloc::format!("Net: portal -> {}", crate::net::portal::state().as_str()).as_str());
         println(
             alloc::format!(
                 "Net: Perfil fija -> {}.{}.{}.{}/{} gw {}.{}.{}.{}",
@@ -2423,26 +2804,26 @@ fn handle_command(cmd: &str, fat: &mut crate::fat32::Fat32, current_cluster: &mu
         with_stdout(|out| {
             let _ = writeln!(out, "Memory statistics:");
             let _ = writeln!(out, "  regions:              {}", stats.regions);
-            let _ = writeln!(out, "  total pages:          {}", stats.total_pages);
-            let _ = writeln!(out, "  conventional pages:   {}", stats.conventional_pages);
-            let _ = writeln!(out, "  reserved pages:       {}", stats.reserved_pages);
+            let _ = writeln!(out, "  total pages:          {}", i18n::format::integer(stats.total_pages));
+            let _ = writeln!(out, "  conventional pages:   {}", i18n::format::integer(stats.conventional_pages));
+            let _ = writeln!(out, "  reserved pages:       {}", i18n::format::integer(stats.reserved_pages));
             let _ = writeln!(
                 out,
-                "  heap reservado:       {} MiB ({} bytes)",
-                heap_bytes / (1024 * 1024),
-                heap_bytes
+                "  heap reservado:       {} ({} bytes)",
+                i18n::format::size(heap_bytes),
+                i18n::format::integer(heap_bytes)
             );
             let _ = writeln!(
                 out,
-                "  heap reservado tarea: {} MiB ({} bytes)",
-                heap_reserved / (1024 * 1024),
-                heap_reserved
+                "  heap reservado tarea: {} ({} bytes)",
+                i18n::format::size(heap_reserved),
+                i18n::format::integer(heap_reserved)
             );
             let _ = writeln!(
                 out,
-                "  largest conventional: {} pages ({} MiB)",
-                stats.largest_conventional_pages,
-                (stats.largest_conventional_pages * memory::PAGE_SIZE) / (1024 * 1024)
+                "  largest conventional: {} pages ({})",
+                i18n::format::integer(stats.largest_conventional_pages),
+                i18n::format::size(stats.largest_conventional_pages * memory::PAGE_SIZE)
             );
             let _ = writeln!(
                 out,
@@ -2501,6 +2882,8 @@ fn handle_command(cmd: &str, fat: &mut crate::fat32::Fat32, current_cluster: &mu
             let _ = writeln!(out, "Scheduler:");
             let _ = writeln!(out, "  tick={} dispatches={} cursor={}", s.tick, s.dispatches, s.cursor);
             let _ = writeln!(out, "  tasks={}", s.task_count);
+            let (live, ready) = executor::task_counts();
+            let _ = writeln!(out, "  async tasks={} ready={}", live, ready);
 
             let mut i = 0;
             while i < s.task_count {
@@ -2734,7 +3117,7 @@ pub(crate) fn launch_doom_uefi() -> core::result::Result<&'static str, String> {
 fn read_efi_from_mounted_fat_path(path: &str, max_size: usize) -> Result<Vec<u8>, String> {
     use crate::fs::FileType;
 
-    let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
     if fat.root_cluster < 2 {
         return Err(String::from("FAT no montado"));
     }
@@ -2835,7 +3218,7 @@ fn collect_preferred_launch_handles(
 
     let mut handles: Vec<uefi::Handle> = Vec::new();
     let current = current_boot_device_handle();
-    let mounted = unsafe { crate::fat32::GLOBAL_FAT.uefi_block_handle };
+    let mounted = unsafe { crate::fat32::GLOBAL_FAT.get().uefi_block_handle };
     let installed = find_installed_redux_handle_with_retry(current);
     let current_is_removable = current
         .and_then(|handle| handle_is_removable(handle))
@@ -3077,6 +3460,10 @@ pub(crate) fn launch_uefi_shell() -> core::result::Result<&'static str, String>
 }
 
 pub(crate) fn restore_gui_after_external_app() -> bool {
+    if framebuffer::is_headless() {
+        // The RAM framebuffer is ours; nothing can have changed its mode.
+        return true;
+    }
     // Some UEFI apps switch GOP mode. Re-capture framebuffer before GUI repaints.
     uefi::boot::stall(20_000);
     let Some(info) = capture_framebuffer_info() else {
@@ -3085,6 +3472,8 @@ pub(crate) fn restore_gui_after_external_app() -> bool {
     framebuffer::init(info);
     let _ = framebuffer::enable_backbuffer();
     input::reset_mouse_uefi();
+    gamepad::reset_uefi();
+    touch::reset_uefi();
     true
 }
 
@@ -3176,15 +3565,18 @@ fn handle_fs_command(cmd: &str, fat: &mut crate::fat32::Fat32, current_cluster:
         }
 
         println("Detected BlockIO devices:");
+        let names = crate::fs::vfs::block_devices();
         for dev in devices.iter() {
             let media = if dev.removable { "USB" } else { "NVME/HDD" };
             let scope = if dev.logical_partition { "part" } else { "disk" };
             let fs = dev.fs_kind.as_str();
+            let name = names.iter().find(|n| n.index == dev.index).map(|n| n.name.as_str()).unwrap_or("?");
             with_stdout(|out| {
                 let _ = writeln!(
                     out,
-                    "  [{}] {} {}  {} MiB  fs={}  lba={}",
+                    "  [{}] /dev/{} {} {}  {} MiB  fs={}  lba={}",
                     dev.index,
+                    name,
                     media,
                     scope,
                     dev.total_mib,
@@ -3193,7 +3585,7 @@ fn handle_fs_command(cmd: &str, fat: &mut crate::fat32::Fat32, current_cluster:
                 );
             });
         }
-        println("Use 'mount <index>' only on entries with fs=FAT32 or fs=EXFAT.");
+        println("Use 'mount <index>' (or 'mount /dev/<name> <dir>') only on entries with fs=FAT32 or fs=EXFAT.");
         return true;
     }
 
@@ -3229,11 +3621,51 @@ fn handle_fs_command(cmd: &str, fat: &mut crate::fat32::Fat32, current_cluster:
         return true;
     }
 
+    if cmd == "df" {
+        if fat.init_status != crate::fat32::InitStatus::Success {
+            println(i18n::tr("df.no_volume").as_str());
+            return true;
+        }
+        let Some(free) = fat.free_clusters() else {
+            println(i18n::tr("df.fat32_only").as_str());
+            return true;
+        };
+        // Bytes first: clusters can be as small as 512 bytes.
+        let cluster_bytes = fat.sectors_per_cluster as u64 * fat.bytes_per_sector as u64;
+        let total = fat.total_clusters() as u64;
+        let used = total.saturating_sub(free as u64);
+        let mib = |clusters: u64| clusters * cluster_bytes / (1024 * 1024);
+        println(
+            i18n::trf(
+                "df.summary",
+                &[
+                    &fat_label_to_string(&fat.volume_label),
+                    &mib(total),
+                    &mib(used),
+                    &mib(free as u64),
+                    &free,
+                    &cluster_bytes,
+                ],
+            )
+            .as_str(),
+        );
+        return true;
+    }
+
+    // Mount table and paths below other mounts (/host, /sys, /dev, /data...).
+    let (verb, args) = cmd.split_once(' ').unwrap_or((cmd, ""));
+    if let Some(lines) = crate::fs::vfs::command_lines(verb, args) {
+        for line in lines.iter() {
+            println(line.as_str());
+        }
+        return true;
+    }
+
     if let Some(raw_idx) = cmd.strip_prefix("mount ") {
         let idx = match raw_idx.trim().parse::<usize>() {
             Ok(v) => v,
             Err(_) => {
-                println("Usage: mount <index>   (see 'disks').");
+                println("Usage: mount <index> | mount <device> <dir>   (see 'disks').");
                 return true;
             }
         };
@@ -3276,6 +3708,10 @@ fn handle_fs_command(cmd: &str, fat: &mut crate::fat32::Fat32, current_cluster:
         }
         
         if *current_cluster == 0 { *current_cluster = fat.root_cluster; }
+        if let Err(e) = perm::authorize_list(fat, *current_cluster) {
+            println(alloc::format!("ls: {}", e).as_str());
+            return true;
+        }
 
         if let Ok(entries) = fat.read_dir_entries(*current_cluster) {
             println("Files:");
@@ -3283,22 +3719,25 @@ fn handle_fs_command(cmd: &str, fat: &mut crate::fat32::Fat32, current_cluster:
             // Print Volume Label only at root
             if *current_cluster == fat.root_cluster && fat.volume_label[0] != 0 {
                 let label = core::str::from_utf8(&fat.volume_label).unwrap_or("UNKNOWN");
-                with_stdout(|out| {
-                        let _ = writeln!(out, "  [VOL ] {}", label);
-                });
+                println(alloc::format!("  [VOL ] {}", label).as_str());
             }
 
+            let links = fsmeta::links_in(fat, *current_cluster);
             let mut count = 0;
             for entry in entries.iter() {
                 if entry.valid {
                     let name = entry.full_name();
+                    let lower = name.to_ascii_lowercase();
+                    if let Some((_, target)) = links.iter().find(|(link, _)| *link == lower) {
+                        println(alloc::format!("  [LINK] {} -> {}", name.as_str(), target.as_str()).as_str());
+                        count += 1;
+                        continue;
+                    }
                     let type_str = match entry.file_type {
                         crate::fs::FileType::Directory => "DIR ",
                         crate::fs::FileType::File => "FILE",
                     };
-                    with_stdout(|out| {
-                        let _ = writeln!(out, "  [{}] {} ({} bytes)", type_str, name.as_str(), entry.size);
-                    });
+                    println(alloc::format!("  [{}] {} ({} bytes)", type_str, name.as_str(), entry.size).as_str());
                     count += 1;
                 }
             }
@@ -3365,12 +3804,55 @@ fn handle_fs_command(cmd: &str, fat: &mut crate::fat32::Fat32, current_cluster:
                  }
              }
              if !found {
-                 println("Directory not found.");
+                 // Links and multi-part paths go through the VFS metadata.
+                 match fsmeta::resolve_dir(fat, *current_cluster, target) {
+                     Ok(cluster) => *current_cluster = cluster,
+                     Err(_) => println("Directory not found."),
+                 }
              }
          }
          return true;
     }
 
+    for verb in ["ln", "readlink", "xattr"] {
+        let Some(args) = cmd.strip_prefix(verb).filter(|rest| rest.is_empty() || rest.starts_with(' ')) else {
+            continue;
+        };
+        if fat.bytes_per_sector == 0 {
+            if !fat.init() { println("FAT32/exFAT Init Failed"); return true; }
+            *current_cluster = fat.root_cluster;
+        }
+        for line in fsmeta::command_lines(fat, *current_cluster, verb, args).iter() {
+            println(line.as_str());
+        }
+        return true;
+    }
+
+    for verb in ["chmod", "chown", "stat"] {
+        let Some(args) = cmd.strip_prefix(verb).filter(|rest| rest.is_empty() || rest.starts_with(' ')) else {
+            continue;
+        };
+        if fat.bytes_per_sector == 0 {
+            if !fat.init() { println("FAT32/exFAT Init Failed"); return true; }
+            *current_cluster = fat.root_cluster;
+        }
+        for line in perm::command_lines(fat, *current_cluster, verb, args).iter() {
+            println(line.as_str());
+        }
+        return true;
+    }
+
+    if cmd == "chkdsk" || cmd.starts_with("chkdsk ") {
+        if fat.bytes_per_sector == 0 {
+            if !fat.init() { println("FAT32/exFAT Init Failed"); return true; }
+            *current_cluster = fat.root_cluster;
+        }
+        for line in chkdsk::command_lines(fat, &cmd["chkdsk".len()..]).iter() {
+            println(line.as_str());
+        }
+        return true;
+    }
+
     if let Some(filename) = cmd.strip_prefix("cat ") {
         if fat.bytes_per_sector == 0 {
              if !fat.init() { println("FAT32/exFAT Init Failed"); return true; }
@@ -3381,6 +3863,18 @@ fn handle_fs_command(cmd: &str, fat: &mut crate::fat32::Fat32, current_cluster:
             let mut found = false;
             for entry in entries.iter() {
                 if entry.valid && entry.matches_name(filename.trim()) {
+                    found = true;
+                    if let Err(e) = perm::authorize_open(fat, *current_cluster, &entry.full_name()) {
+                        println(alloc::format!("cat: {}", e).as_str());
+                        break;
+                    }
+                    let entry = match fsmeta::follow(fat, *current_cluster, entry) {
+                        Ok(entry) => entry,
+                        Err(e) => {
+                            println(alloc::format!("cat: {}", e).as_str());
+                            break;
+                        }
+                    };
                     let target = (entry.size as usize).min(16 * 1024);
                     let mut buffer = Vec::new();
                     buffer.resize(target, 0);
@@ -3396,7 +3890,6 @@ fn handle_fs_command(cmd: &str, fat: &mut crate::fat32::Fat32, current_cluster:
                         }
                         Err(_) => println("Read error."),
                     }
-                    found = true;
                     break;
                 }
             }
@@ -3498,24 +3991,27 @@ fn format_disk() {
     sector[510] = 0x55; sector[511] = 0xAA;
     
     use crate::virtio::block;
-    if block::write(0, &sector) {
+    if block::write_blocks(0, &sector) {
         println("Format: Wrote MBR/BPB to Sector 0.");
     } else {
         println("Format: Failed to write Sector 0.");
     }
     
-    // Clean FAT1 (Sector 32 .. 32+1024)
-    let mut fat_sector = [0u8; 512];
+    // Clean FAT1 (Sector 32 .. 32+1024) in one pass
+    let mut fat = alloc::vec![0u8; 1024 * 512];
     // Entry 0
-    fat_sector[0] = 0xF8; fat_sector[1] = 0xFF; fat_sector[2] = 0xFF; fat_sector[3] = 0x0F;
+    fat[0] = 0xF8; fat[1] = 0xFF; fat[2] = 0xFF; fat[3] = 0x0F;
     // Entry 1
-    fat_sector[4] = 0xFF; fat_sector[5] = 0xFF; fat_sector[6] = 0xFF; fat_sector[7] = 0x0F; 
+    fat[4] = 0xFF; fat[5] = 0xFF; fat[6] = 0xFF; fat[7] = 0x0F;
     // Entry 2 (Root Dir) - End of Chain (0x0FFFFFFF)
-    fat_sector[8] = 0xFF; fat_sector[9] = 0xFF; fat_sector[10] = 0xFF; fat_sector[11] = 0x0F;
-    
-    // Write detailed FAT sector
+    fat[8] = 0xFF; fat[9] = 0xFF; fat[10] = 0xFF; fat[11] = 0x0F;
+    // Clusters 3 (README.TXT), 4 (DOCS dir) and 5 (TEST.TXT) - End of Chain
+    for entry in fat[12..24].chunks_mut(4) {
+        entry.copy_from_slice(&[0xFF, 0xFF, 0xFF, 0x0F]);
+    }
+
     // Start of FAT1 = Reserved = 32.
-    block::write(32, &fat_sector);
+    block::write_blocks(32, &fat);
     
     // Clean the root directory cluster (cluster 2 = sector 1088)
     // Data starts at sector 32 + (2 * 1024) = 2080
@@ -3542,21 +4038,7 @@ fn format_disk() {
     root_sector[90] = 5; root_sector[91] = 0; // Cluster low word
     root_sector[92] = 200; root_sector[93] = 1; root_sector[94] = 0; root_sector[95] = 0; // Size 456 bytes (0x1C8)
     
-    block::write(root_lba, &root_sector);
-    
-    // Update FAT to mark clusters 3, 4, 5 as end-of-chain
-    let mut fat_update = [0u8; 512];
-    // Read current FAT sector
-    block::read(32, &mut fat_update);
-    
-    // Cluster 3 (README.TXT) - End of Chain
-    fat_update[12] = 0xFF; fat_update[13] = 0xFF; fat_update[14] = 0xFF; fat_update[15] = 0x0F;
-    // Cluster 4 (DOCS dir) - End of Chain
-    fat_update[16] = 0xFF; fat_update[17] = 0xFF; fat_update[18] = 0xFF; fat_update[19] = 0x0F;
-    // Cluster 5 (TEST.TXT) - End of Chain
-    fat_update[20] = 0xFF; fat_update[21] = 0xFF; fat_update[22] = 0xFF; fat_update[23] = 0x0F;
-    
-    block::write(32, &fat_update);
+    block::write_blocks(root_lba, &root_sector);
     
     println("Format: Created sample files (README.TXT, DOCS/, TEST.TXT)");
 }
@@ -3564,7 +4046,7 @@ fn format_disk() {
 fn enter_runtime_kernel(mode: runtime::RuntimeMode) -> ! {
     println("Preparing runtime handoff...");
 
-    let fb = match capture_framebuffer_info() {
+    let fb = match display_framebuffer_info() {
         Some(info) => info,
         None => {
             println("Failed to capture GOP framebuffer. Rebooting...");
@@ -3588,7 +4070,7 @@ fn enter_runtime_kernel(mode: runtime::RuntimeMode) -> ! {
 fn enter_runtime_uefi() -> ! {
     println("Preparing UEFI runtime (Boot Services remain active)...");
 
-    let fb = match capture_framebuffer_info() {
+    let fb = match display_framebuffer_info() {
         Some(info) => info,
         None => {
             println("Failed to capture GOP framebuffer. Rebooting...");
@@ -3855,6 +4337,25 @@ fn desktop_frame_stall_us_for_hz(hz: u32) -> u64 {
     tuned.clamp(DESKTOP_FRAME_STALL_US_MIN, DESKTOP_FRAME_STALL_US_MAX)
 }
 
+/// GOP framebuffer, or one in RAM when there is none (or `display.headless`
+/// is `on`) so the desktop still runs headless, seen through `net::remote`.
+fn display_framebuffer_info() -> Option<FramebufferInfo> {
+    let headless = config::get_str(framebuffer::HEADLESS_KEY, "auto");
+    if headless.as_str() != "on" {
+        if let Some(info) = capture_framebuffer_info() {
+            return Some(info);
+        }
+        if headless.as_str() == "off" {
+            return None;
+        }
+    }
+    let size = config::get_str(framebuffer::HEADLESS_SIZE_KEY, "1024x768");
+    let (width, height) = framebuffer::parse_mode(size.as_str()).unwrap_or((1024, 768));
+    let info = framebuffer::ram_info(width, height);
+    println(alloc::format!("Display: sin GOP, framebuffer en RAM de {}x{}.", info.width, info.height).as_str());
+    Some(info)
+}
+
 fn capture_framebuffer_info() -> Option<FramebufferInfo> {
     let handle = match uefi::boot::get_handle_for_protocol::<GraphicsOutput>() {
         Ok(h) => h,
@@ -3889,15 +4390,44 @@ fn capture_framebuffer_info() -> Option<FramebufferInfo> {
     })
 }
 
+/// `gamepad test` in the text shell: print each pad's state as it changes
+/// until a key is pressed.
+fn gamepad_test_live() {
+    for line in gamepad::command_lines("list").iter() {
+        println(line.as_str());
+    }
+    if gamepad::pad_count() == 0 {
+        return;
+    }
+    println("Mueve los sticks o pulsa botones; cualquier tecla termina.");
+    let mut last: Vec<String> = Vec::new();
+    while poll_input_event().is_none() {
+        let lines: Vec<String> = gamepad::snapshot()
+            .iter()
+            .enumerate()
+            .map(|(i, state)| gamepad::state_line(i, state))
+            .collect();
+        for (i, line) in lines.iter().enumerate() {
+            if last.get(i) != Some(line) {
+                println(line.as_str());
+            }
+        }
+        last = lines;
+        uefi::boot::stall(16_000);
+    }
+}
+
 #[derive(Clone, Copy)]
-enum InputEvent {
+pub(crate) enum InputEvent {
     Char(char),
     Enter,
     Backspace,
     Escape,
+    Up,
+    Down,
 }
 
-fn poll_input_event() -> Option<InputEvent> {
+pub(crate) fn poll_input_event() -> Option<InputEvent> {
     uefi::system::with_stdin(|input| match input.read_key().ok().flatten() {
         Some(Key::Printable(c16)) => {
             let ch: char = c16.into();
@@ -3908,6 +4438,8 @@ fn poll_input_event() -> Option<InputEvent> {
             }
         }
         Some(Key::Special(ScanCode::ESCAPE)) => Some(InputEvent::Escape),
+        Some(Key::Special(ScanCode::UP)) => Some(InputEvent::Up),
+        Some(Key::Special(ScanCode::DOWN)) => Some(InputEvent::Down),
         _ => None,
     })
 }
@@ -3915,7 +4447,7 @@ fn poll_input_event() -> Option<InputEvent> {
 mod utils;
 
 
-fn clear_screen() {
+pub(crate) fn clear_screen() {
     if unsafe { QUIET_BOOT } { return; }
     with_stdout(|out| {
         let _ = out.clear();
@@ -3930,6 +4462,25 @@ where
 }
 
 pub fn println(msg: &str) {
+    klog::record_console_line(msg);
+    if !klog::shown_on_console(msg) {
+        return;
+    }
+    if fbcon::is_active() {
+        fbcon::write_line(msg);
+        return;
+    }
+    if unsafe { QUIET_BOOT } { return; }
+    with_stdout(|out| {
+        let _ = writeln!(out, "{}", msg);
+    });
+}
+
+pub fn println_unlogged(msg: &str) {
+    if fbcon::is_active() {
+        fbcon::write_line(msg);
+        return;
+    }
     if unsafe { QUIET_BOOT } { return; }
     with_stdout(|out| {
         let _ = writeln!(out, "{}", msg);
@@ -3937,6 +4488,10 @@ pub fn println(msg: &str) {
 }
 
 pub fn print(msg: &str) {
+    if fbcon::is_active() {
+        fbcon::write(msg);
+        return;
+    }
     if unsafe { QUIET_BOOT } { return; }
     with_stdout(|out| {
         let _ = write!(out, "{}", msg);
@@ -3959,7 +4514,12 @@ pub fn println_hex(n: u64) {
 
 fn prompt() {
     with_stdout(|out| {
-        let _ = write!(out, "redux> ");
+        let prompt = if safemode::active() {
+            "redux [modo seguro]> "
+        } else {
+            "redux> "
+        };
+        let _ = write!(out, "{}", prompt);
     });
 }
 
@@ -3974,8 +4534,26 @@ fn backspace_echo() {
         let _ = write!(out, "\u{8} \u{8}");
     });
 }
+/// Before the desktop starts, wait until the keyboard has been quiet this
+/// long. The Esc that skipped the installer, and its repeats, would otherwise
+/// reach the desktop. This used to be a fixed 300 ms stall.
+const GUI_KEY_SETTLE_US: u64 = 60_000;
+const GUI_KEY_SETTLE_TIMEOUT_US: u64 = 300_000;
+const GUI_KEY_SETTLE_POLL_US: u64 = 5_000;
+
+fn settle_keys_before_gui() {
+    let mut quiet_since = perf::now_us();
+    boottime::wait_until(GUI_KEY_SETTLE_TIMEOUT_US, GUI_KEY_SETTLE_POLL_US, || {
+        let now = perf::now_us();
+        if input::poll_input_uefi().is_some() {
+            quiet_since = now;
+        }
+        now.saturating_sub(quiet_since) >= GUI_KEY_SETTLE_US
+    });
+}
+
 fn start_gui_mode() -> ! {
-    let fb_info = match capture_framebuffer_info() {
+    let fb_info = match display_framebuffer_info() {
         Some(info) => info,
         None => {
             println("Failed to capture GOP for GUI. Returning to shell...");
@@ -3987,7 +4565,7 @@ fn start_gui_mode() -> ! {
     framebuffer::init(fb_info);
     framebuffer::enable_backbuffer();
 
-    let detected_refresh_hz = detect_monitor_refresh_hz();
+    let detected_refresh_hz = boottime::stage("edid_refresh", detect_monitor_refresh_hz);
     runtime::set_irq_timer_target_hz(detected_refresh_hz);
     // UI cadence is tied to monitor refresh, not IRQ timer target.
     // This keeps cursor/input fluido even when IRQ target changes.
@@ -3997,7 +4575,10 @@ fn start_gui_mode() -> ! {
     let (width, height) = framebuffer::dimensions();
     input::set_screen_dimensions(width as u32, height as u32);
     input::reset_mouse_uefi();
-    let mut compositor = gui::compositor::Compositor::new(width, height);
+    gamepad::reset_uefi();
+    touch::reset_uefi();
+    perf::start_desktop(desktop_stall_hz);
+    let mut compositor = boottime::stage("compositor", || gui::compositor::Compositor::new(width, height));
     
     // Create Desktop UI
     let _term_win_id = compositor.create_window("Terminal Shell", 100, 100, 800, 500);
@@ -4010,6 +4591,18 @@ fn start_gui_mode() -> ! {
         );
         compositor.add_window_output(_term_win_id, &smp_msg);
     }
+    if safemode::active() {
+        for line in safemode::banner_lines().iter() {
+            compositor.add_window_output(_term_win_id, line);
+        }
+        gui::notifications::post(
+            "safemode",
+            "Modo seguro",
+            "Drivers minimos: sin Intel Xe, Wi-Fi, audio ni NVMe.",
+            gui::notifications::Urgency::Warning,
+        );
+    }
+    compositor.offer_session_restore();
 
     println("Entering Desktop Mode...");
     with_stdout(|out| {
@@ -4049,8 +4642,11 @@ fn start_gui_mode() -> ! {
         }
     }
 
-    let mut current_mouse_x = compositor.mouse_pos.x;
-    let mut current_mouse_y = compositor.mouse_pos.y;
+    gui::event_queue::start_desktop(compositor.mouse_pos.x, compositor.mouse_pos.y);
+    replay::start();
+    gui::interaction::load_config();
+    gui::osk::load_config();
+    gamma::load_config();
     let mut mouse_boost_frames: u8 = 0;
 
     if !framebuffer::enable_backbuffer() {
@@ -4059,7 +4655,7 @@ fn start_gui_mode() -> ! {
 
     // --- Create Default Desktop Shortcuts ---
     unsafe {
-        let fat = &mut crate::fat32::GLOBAL_FAT;
+        let fat = crate::fat32::GLOBAL_FAT.get_mut();
         let root_cluster = fat.root_cluster;
         let mut desktop_cluster = fat
             .resolve_path(root_cluster, "Desktop/")
@@ -4090,8 +4686,14 @@ fn start_gui_mode() -> ! {
         }
     }
 
+    boottime::desktop_ready();
+    trace::mark_boot_done();
+    gui::caret::start();
+
     loop {
         _frame_count += 1;
+        let frame_start = perf::begin_frame();
+        let _frame = trace::scope("gui", "frame");
 
         // Apply runtime mode requests (boot irq / boot poll) from compositor commands.
         irq_mode_active = runtime::service_mode_switch_non_runtime(irq_mode_active);
@@ -4102,92 +4704,30 @@ fn start_gui_mode() -> ! {
         let tick = timer::on_tick();
         scheduler::on_tick(tick);
         syscall::set_runtime_state(tick, true, irq_mode_active);
+        timer::run_due();
+        executor::run_ready();
         
-        let mut had_mouse_activity = false;
-
-        // 1. Poll Mouse first (prioridad de puntero para mantener fluidez al mover/arrastrar).
-        while let Some((dx, dy, wheel_delta, left_btn, right_btn)) = input::poll_mouse_uefi() {
-            current_mouse_x = current_mouse_x.saturating_add(dx);
-            current_mouse_y = current_mouse_y.saturating_add(dy);
-            current_mouse_x = current_mouse_x.clamp(0, width as i32 - 1);
-            current_mouse_y = current_mouse_y.clamp(0, height as i32 - 1);
-            had_mouse_activity |= dx != 0 || dy != 0 || wheel_delta != 0 || left_btn || right_btn;
-
-            use crate::gui::{Event, MouseEvent};
-            compositor.handle_event(Event::Mouse(MouseEvent {
-                x: current_mouse_x,
-                y: current_mouse_y,
-                left_down: left_btn,
-                right_down: right_btn,
-                wheel_delta,
-            }));
-        }
-
-        // 2. Poll input (Keyboard)
-        while let Some(input_event) = input::poll_input_uefi() {
-            use crate::gui::{Event, KeyboardEvent, SpecialKey};
-            let gui_event = match input_event {
-                input::RuntimeInput::Char(ch) => Some(Event::Keyboard(KeyboardEvent {
-                    key: Some(ch),
-                    special: None,
-                    down: true,
-                })),
-                input::RuntimeInput::Enter => Some(Event::Keyboard(KeyboardEvent {
-                    key: Some('\n'),
-                    special: None,
-                    down: true,
-                })),
-                input::RuntimeInput::Backspace => Some(Event::Keyboard(KeyboardEvent {
-                    key: Some('\x08'),
-                    special: None,
-                    down: true,
-                })),
-                input::RuntimeInput::Key(input::RuntimeKey::Esc) => Some(Event::Keyboard(
-                    KeyboardEvent {
-                        key: Some('\x1b'),
-                        special: None,
-                        down: true,
-                    },
-                )),
-                input::RuntimeInput::Key(input::RuntimeKey::Up) => Some(Event::Keyboard(
-                    KeyboardEvent {
-                        key: None,
-                        special: Some(SpecialKey::Up),
-                        down: true,
-                    },
-                )),
-                input::RuntimeInput::Key(input::RuntimeKey::Down) => Some(Event::Keyboard(
-                    KeyboardEvent {
-                        key: None,
-                        special: Some(SpecialKey::Down),
-                        down: true,
-                    },
-                )),
-                input::RuntimeInput::Key(input::RuntimeKey::Left) => Some(Event::Keyboard(
-                    KeyboardEvent {
-                        key: None,
-                        special: Some(SpecialKey::Left),
-                        down: true,
-                    },
-                )),
-                input::RuntimeInput::Key(input::RuntimeKey::Right) => Some(Event::Keyboard(
-                    KeyboardEvent {
-                        key: None,
-                        special: Some(SpecialKey::Right),
-                        down: true,
-                    },
-                )),
-                _ => None,
-            };
-
-            if let Some(e) = gui_event {
-                compositor.handle_event(e);
-            }
-        }
+        // 1. Poll mouse, keyboard and gamepads into the event queue, then hand
+        // everything queued (including input that arrived during the last
+        // paint) to the compositor.
+        gui::event_queue::pump();
+        let had_mouse_activity = gui::event_queue::take_mouse_activity();
+        compositor.drain_input_events();
+        // Lid and power buttons, also while suspended: they are what wakes it.
+        compositor.service_power_events();
+        perf::record(perf::Phase::Input, frame_start);
 
         if !compositor.is_suspended() {
+            let net_start = perf::now_us();
+            trace::begin("gui", "net_poll");
             crate::net::poll();
+            trace::end("gui", "net_poll");
+            perf::record(perf::Phase::Net, net_start);
+            let layout_start = perf::now_us();
+            trace::begin("gui", "services");
             compositor.service_background_tasks();
+            trace::end("gui", "services");
+            perf::record(perf::Phase::Layout, layout_start);
 
             // Periodic repaint every ~16ms for background services
             // (terminal streams, linux runloop, copy progress, heartbeat)
@@ -4196,8 +4736,9 @@ fn start_gui_mode() -> ! {
             }
         }
 
-        // 4. Paint — only when something changed
-        if compositor.needs_repaint() {
+        // 4. Paint — only when something changed, at most desktop.fps_cap times a second
+        let painted = compositor.needs_repaint() && perf::paint_due();
+        if painted {
             compositor.paint();
 
             // 5. Heartbeat (Blinking dot in corner to show system is alive)
@@ -4206,8 +4747,11 @@ fn start_gui_mode() -> ! {
             }
         }
 
-        current_mouse_x = compositor.mouse_pos.x;
-        current_mouse_y = compositor.mouse_pos.y;
+        if perf::end_frame(painted) && perf::hud_visible() {
+            compositor.mark_dirty();
+        }
+
+        gui::event_queue::sync_cursor(compositor.mouse_pos.x, compositor.mouse_pos.y);
 
         // Keep firmware yield aligned with display target while preserving low-latency input.
         if (_frame_count & 31) == 0 {
@@ -4233,14 +4777,22 @@ fn start_gui_mode() -> ! {
 
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
-    println("");
-    println("!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!");
-    println("KERNEL PANIC!");
+    // Nothing here may allocate or take the klog lock: the panic can come
+    // from an exception handler or from inside the allocator.
+    driver_guard::recover_panic(info);
+    let mut line: klog::FixedBuf<256> = klog::FixedBuf::new();
+    println_unlogged("");
+    println_unlogged("!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!");
+    println_unlogged("KERNEL PANIC!");
     if let Some(location) = info.location() {
-        println(&alloc::format!("Location: {}:{}:{}", location.file(), location.line(), location.column()));
-    }
-    println(&alloc::format!("Message: {}", info.message()));
-    println("!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!");
+        let _ = write!(line, "Location: {}:{}:{}", location.file(), location.line(), location.column());
+        println_unlogged(line.as_str());
+        line.clear();
+    }
+    let _ = write!(line, "Message: {}", info.message());
+    println_unlogged(line.as_str());
+    klog::klog_irq!(klog::Level::Critical, "KERNEL PANIC: {}", info.message());
+    println_unlogged("!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!");
     
     loop {
         core::hint::spin_loop();
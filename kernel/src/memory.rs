@@ -33,6 +33,19 @@ impl Region {
         self.next_page = self.next_page.saturating_add(1);
         Some(addr)
     }
+
+    /// `count` consecutive pages ending at or below `limit`.
+    fn alloc_run_below(&mut self, count: u64, limit: u64) -> Option<u64> {
+        if self.pages.saturating_sub(self.next_page) < count {
+            return None;
+        }
+        let addr = self.start_phys + self.next_page.saturating_mul(PAGE_SIZE);
+        if addr.saturating_add(count.saturating_mul(PAGE_SIZE)) > limit {
+            return None;
+        }
+        self.next_page = self.next_page.saturating_add(count);
+        Some(addr)
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -143,6 +156,17 @@ impl FrameAllocator {
         None
     }
 
+    fn alloc_run_below(&mut self, count: u64, limit: u64) -> Option<u64> {
+        for idx in 0..self.region_count {
+            if let Some(addr) = self.regions[idx].alloc_run_below(count, limit) {
+                self.allocations = self.allocations.saturating_add(1);
+                return Some(addr);
+            }
+        }
+        self.failed_allocations = self.failed_allocations.saturating_add(1);
+        None
+    }
+
     fn state(&self) -> AllocatorState {
         AllocatorState {
             tracked_regions: self.region_count,
@@ -236,6 +260,45 @@ pub fn allocate_dma_page32() -> Option<u64> {
     None
 }
 
+/// `count` physically contiguous pages below 4GiB, for DMA buffers larger
+/// than a page (jumbo frames).
+pub fn allocate_dma_pages32(count: usize) -> Option<u64> {
+    if count <= 1 {
+        return allocate_dma_page32();
+    }
+    unsafe { ALLOCATOR.alloc_run_below(count as u64, 0x1_0000_0000) }
+}
+
 pub fn allocator_state() -> AllocatorState {
     unsafe { ALLOCATOR.state() }
 }
+
+crate::selftest::kernel_tests! {
+    "memory";
+
+    fn map_has_conventional_memory() {
+        let stats = stats();
+        crate::selftest::ensure(stats.regions > 0, "mapa de memoria vacio")?;
+        crate::selftest::ensure(
+            stats.conventional_pages > 0 && stats.conventional_pages <= stats.total_pages,
+            "paginas convencionales fuera de rango",
+        )
+    }
+
+    fn frames_are_aligned_and_distinct() {
+        // Frames are never returned to the allocator; this costs two pages per run.
+        let a = alloc_frame().ok_or_else(|| alloc::string::String::from("sin frames libres"))?;
+        let b = alloc_frame().ok_or_else(|| alloc::string::String::from("sin frames libres"))?;
+        crate::selftest::ensure(a % 4096 == 0 && b % 4096 == 0, "frame no alineado a 4 KiB")?;
+        crate::selftest::ensure(a != b, "frame entregado dos veces")
+    }
+
+    fn heap_roundtrip() {
+        let mut buf = alloc::vec![0u8; 64 * 1024];
+        for (i, b) in buf.iter_mut().enumerate() {
+            *b = (i * 7 + 3) as u8;
+        }
+        let ok = buf.iter().enumerate().all(|(i, b)| *b == (i * 7 + 3) as u8);
+        crate::selftest::ensure(ok, "patron del heap corrupto")
+    }
+}
@@ -0,0 +1,409 @@
+//! Neighbor table: ARP for IPv4, neighbor discovery for IPv6.
+//!
+//! smoltcp keeps its neighbor cache private, so `net arp` shows a table kept
+//! next to it: every received frame passes through `observe` on its way to
+//! the stack, and the sender of an ARP or NDP packet is recorded with the MAC
+//! it claimed. Static entries live in the config as `net.arp.<a-b-c-d>` and
+//! are fed to smoltcp as ARP replies addressed to us, again before its 60 s
+//! cache lifetime runs out. `net arp flush` empties both tables.
+//!
+//! Whenever the interface gets an address (DHCP lease or renewal, saved
+//! lease, static setup, link back up) a gratuitous ARP goes out twice so
+//! switches and neighbors learn the new binding at once.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+
+use redux_netparse::neighbor::{
+    arp_frame, gratuitous_arp, mac_text, observe_frame, parse_mac, NeighborIp, NeighborObservation, NeighborSource,
+    ARP_OP_REPLY,
+};
+use smoltcp::iface::Interface;
+use smoltcp::phy::{Device, TxToken};
+use smoltcp::time::Instant;
+use smoltcp::wire::IpAddress;
+
+use super::ReduxPhy;
+use crate::config::ConfigValue;
+use crate::println;
+use crate::spinlock::SpinLock;
+
+pub const ARP_STATIC_KEY_PREFIX: &str = "net.arp.";
+/// Learned neighbors kept at once; the one heard from longest ago makes room.
+const NEIGHBOR_TABLE_MAX: usize = 64;
+/// smoltcp forgets a neighbor after 60 s; static entries are fed again first.
+const STATIC_REFRESH_TICKS: u64 = 3_000;
+/// RFC 5227: two announcements, 2 s apart.
+const ANNOUNCE_COUNT: u8 = 2;
+const ANNOUNCE_INTERVAL_TICKS: u64 = 200;
+const INJECT_QUEUE_MAX: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NeighborEntry {
+    pub ip: NeighborIp,
+    pub mac: [u8; 6],
+    pub source: NeighborSource,
+    pub seen_ticks: u64,
+}
+
+static NEIGHBORS: SpinLock<Vec<NeighborEntry>> = SpinLock::new(Vec::new());
+static LOCAL_MAC: SpinLock<[u8; 6]> = SpinLock::new([0; 6]);
+/// Frames waiting to be handed to smoltcp as if they came off the wire.
+static INJECT_QUEUE: SpinLock<Vec<Vec<u8>>> = SpinLock::new(Vec::new());
+static STATIC_DUE_TICKS: AtomicU64 = AtomicU64::new(0);
+static FLUSH_PENDING: AtomicBool = AtomicBool::new(false);
+static ANNOUNCE_LEFT: AtomicU8 = AtomicU8::new(0);
+static ANNOUNCE_DUE_TICKS: AtomicU64 = AtomicU64::new(0);
+
+pub(super) fn set_local_mac(mac: [u8; 6]) {
+    *LOCAL_MAC.lock() = mac;
+}
+
+fn record_into(table: &mut Vec<NeighborEntry>, seen: NeighborObservation, now_ticks: u64) {
+    if let Some(entry) = table.iter_mut().find(|entry| entry.ip == seen.ip) {
+        entry.mac = seen.mac;
+        entry.source = seen.source;
+        entry.seen_ticks = now_ticks;
+        return;
+    }
+    if table.len() >= NEIGHBOR_TABLE_MAX {
+        if let Some(idx) = table
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, entry)| entry.seen_ticks)
+            .map(|(idx, _)| idx)
+        {
+            table.swap_remove(idx);
+        }
+    }
+    table.push(NeighborEntry {
+        ip: seen.ip,
+        mac: seen.mac,
+        source: seen.source,
+        seen_ticks: now_ticks,
+    });
+}
+
+/// Note the neighbor a received frame vouches for; runs for every frame.
+pub(super) fn observe(frame: &[u8]) {
+    if let Some(seen) = observe_frame(frame) {
+        record_into(&mut NEIGHBORS.lock(), seen, crate::timer::ticks());
+    }
+}
+
+/// Next frame to feed to smoltcp, if any.
+pub(super) fn take_injected() -> Option<Vec<u8>> {
+    let mut queue = INJECT_QUEUE.lock();
+    if queue.is_empty() {
+        None
+    } else {
+        Some(queue.remove(0))
+    }
+}
+
+/// The interface has a new (or renewed) address: announce it.
+pub fn announce() {
+    ANNOUNCE_LEFT.store(ANNOUNCE_COUNT, Ordering::Relaxed);
+    ANNOUNCE_DUE_TICKS.store(crate::timer::ticks(), Ordering::Relaxed);
+    // A new subnet may make static entries usable.
+    STATIC_DUE_TICKS.store(0, Ordering::Relaxed);
+}
+
+fn static_key(ip: [u8; 4]) -> String {
+    alloc::format!("{}{}-{}-{}-{}", ARP_STATIC_KEY_PREFIX, ip[0], ip[1], ip[2], ip[3])
+}
+
+fn ip_from_static_key(key: &str) -> Option<[u8; 4]> {
+    let dotted = key.strip_prefix(ARP_STATIC_KEY_PREFIX)?.replace('-', ".");
+    super::parse_ipv4_octets(dotted.as_str())
+}
+
+/// Static entries from the config, sorted by address.
+pub fn static_entries() -> Vec<([u8; 4], [u8; 6])> {
+    let mut out: Vec<([u8; 4], [u8; 6])> = crate::config::list(ARP_STATIC_KEY_PREFIX)
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let ConfigValue::Str(text) = value else {
+                return None;
+            };
+            Some((ip_from_static_key(key.as_str())?, parse_mac(text.as_str())?))
+        })
+        .collect();
+    out.sort();
+    out
+}
+
+pub(super) fn same_subnet(a: [u8; 4], b: [u8; 4], prefix_len: u8) -> bool {
+    let mask = if prefix_len == 0 {
+        0
+    } else {
+        u32::MAX << (32 - prefix_len.min(32) as u32)
+    };
+    u32::from_be_bytes(a) & mask == u32::from_be_bytes(b) & mask
+}
+
+pub(super) fn local_ipv4(iface: &Interface) -> Option<([u8; 4], u8)> {
+    iface.ip_addrs().iter().find_map(|cidr| match cidr.address() {
+        IpAddress::Ipv4(ip) if !ip.is_unspecified() => Some((ip.0, cidr.prefix_len())),
+        _ => None,
+    })
+}
+
+fn send_frame(phy: &mut ReduxPhy, timestamp: Instant, frame: &[u8]) -> bool {
+    let Some(token) = phy.transmit(timestamp) else {
+        return false;
+    };
+    token.consume(frame.len(), |buf| buf.copy_from_slice(frame));
+    true
+}
+
+/// Flushes, static refreshes and announcements; runs from `net::poll` after
+/// the interface was polled.
+pub(super) fn pump(iface: &mut Interface, phy: &mut ReduxPhy, timestamp: Instant, now_ticks: u64) {
+    if FLUSH_PENDING.swap(false, Ordering::Relaxed) {
+        // Rewriting the address list is the only way to empty smoltcp's cache.
+        iface.update_ip_addrs(|_| {});
+        NEIGHBORS.lock().clear();
+        STATIC_DUE_TICKS.store(0, Ordering::Relaxed);
+    }
+    let Some((local_ip, prefix_len)) = local_ipv4(iface) else {
+        return;
+    };
+    let local_mac = *LOCAL_MAC.lock();
+
+    if now_ticks >= STATIC_DUE_TICKS.load(Ordering::Relaxed) {
+        STATIC_DUE_TICKS.store(now_ticks + STATIC_REFRESH_TICKS, Ordering::Relaxed);
+        // smoltcp only learns from ARP aimed at our address and from
+        // senders on our subnet.
+        for (ip, mac) in static_entries() {
+            if !same_subnet(ip, local_ip, prefix_len) {
+                continue;
+            }
+            let mut queue = INJECT_QUEUE.lock();
+            if queue.len() < INJECT_QUEUE_MAX {
+                queue.push(arp_frame(ARP_OP_REPLY, local_mac, mac, ip, local_mac, local_ip));
+            }
+        }
+    }
+
+    let announce_left = ANNOUNCE_LEFT.load(Ordering::Relaxed);
+    if announce_left > 0 && now_ticks >= ANNOUNCE_DUE_TICKS.load(Ordering::Relaxed) {
+        if !send_frame(phy, timestamp, gratuitous_arp(local_mac, local_ip).as_slice()) {
+            return;
+        }
+        if announce_left == ANNOUNCE_COUNT {
+            println(
+                alloc::format!(
+                    "Net: ARP gratuito -> {}.{}.{}.{} en {}",
+                    local_ip[0],
+                    local_ip[1],
+                    local_ip[2],
+                    local_ip[3],
+                    mac_text(&local_mac)
+                )
+                .as_str(),
+            );
+        }
+        ANNOUNCE_LEFT.store(announce_left - 1, Ordering::Relaxed);
+        ANNOUNCE_DUE_TICKS.store(now_ticks + ANNOUNCE_INTERVAL_TICKS, Ordering::Relaxed);
+    }
+}
+
+fn sort_key(ip: &NeighborIp) -> (u8, [u8; 16]) {
+    match ip {
+        NeighborIp::V4(ip) => {
+            let mut key = [0u8; 16];
+            key[..4].copy_from_slice(ip);
+            (0, key)
+        }
+        NeighborIp::V6(ip) => (1, *ip),
+    }
+}
+
+/// Learned neighbors, IPv4 first, by address.
+pub fn neighbors() -> Vec<NeighborEntry> {
+    let mut rows = NEIGHBORS.lock().clone();
+    rows.sort_by_key(|entry| sort_key(&entry.ip));
+    rows
+}
+
+fn gateway_octets() -> Option<[u8; 4]> {
+    match super::get_gateway() {
+        Some(IpAddress::Ipv4(ip)) => Some(ip.0),
+        _ => None,
+    }
+}
+
+fn list_lines() -> Vec<String> {
+    let mut out = Vec::new();
+    let statics = static_entries();
+    let learned = neighbors();
+    let gateway = gateway_octets();
+    let now = crate::timer::ticks();
+    if statics.is_empty() && learned.is_empty() {
+        out.push(String::from("net arp: tabla de vecinos vacia"));
+    } else {
+        out.push(String::from(
+            "IP                                       MAC                Tipo      Visto",
+        ));
+    }
+    let gateway_mark = |ip: &NeighborIp| {
+        if matches!((ip, gateway), (NeighborIp::V4(ip), Some(gw)) if *ip == gw) {
+            " (puerta de enlace)"
+        } else {
+            ""
+        }
+    };
+    for (ip, mac) in statics.iter() {
+        let ip = NeighborIp::V4(*ip);
+        out.push(alloc::format!(
+            "{:<40} {}  estatica  -{}",
+            ip.text(),
+            mac_text(mac),
+            gateway_mark(&ip)
+        ));
+    }
+    for entry in learned.iter() {
+        if matches!(entry.ip, NeighborIp::V4(ip) if statics.iter().any(|(static_ip, _)| *static_ip == ip)) {
+            continue;
+        }
+        out.push(alloc::format!(
+            "{:<40} {}  {:<8}  hace {} s{}",
+            entry.ip.text(),
+            mac_text(&entry.mac),
+            match entry.source {
+                NeighborSource::Arp => "arp",
+                NeighborSource::Ndp => "ndp",
+            },
+            now.saturating_sub(entry.seen_ticks) / 100,
+            gateway_mark(&entry.ip)
+        ));
+    }
+    if let Some(gw) = gateway {
+        let known =
+            statics.iter().any(|(ip, _)| *ip == gw) || learned.iter().any(|entry| entry.ip == NeighborIp::V4(gw));
+        if !known {
+            out.push(alloc::format!(
+                "net arp: la puerta de enlace {} no ha respondido a ARP",
+                NeighborIp::V4(gw).text()
+            ));
+        }
+    }
+    out
+}
+
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut parts = args.split_whitespace();
+    let sub = parts.next().unwrap_or("list");
+    let ip_arg = parts.next();
+    let mac_arg = parts.next();
+
+    if sub.eq_ignore_ascii_case("list") && ip_arg.is_none() {
+        return list_lines();
+    }
+
+    if sub.eq_ignore_ascii_case("flush") && ip_arg.is_none() {
+        FLUSH_PENDING.store(true, Ordering::Relaxed);
+        out.push(String::from(
+            "net arp: tabla de vecinos vaciada (las entradas estaticas se mantienen)",
+        ));
+        return out;
+    }
+
+    if sub.eq_ignore_ascii_case("add") && parts.next().is_none() {
+        let (Some(ip_text), Some(mac_text_arg)) = (ip_arg, mac_arg) else {
+            out.push(String::from("Uso: net arp add <ip> <mac>"));
+            return out;
+        };
+        let Some(ip) = super::parse_ipv4_octets(ip_text) else {
+            out.push(alloc::format!("net arp: IP invalida: {}", ip_text));
+            return out;
+        };
+        let mac = match parse_mac(mac_text_arg) {
+            Some(mac) if mac[0] & 1 == 0 && mac != [0; 6] => mac,
+            _ => {
+                out.push(alloc::format!("net arp: MAC invalida: {}", mac_text_arg));
+                return out;
+            }
+        };
+        if let Err(err) = crate::config::set(static_key(ip).as_str(), ConfigValue::Str(mac_text(&mac))) {
+            out.push(alloc::format!("net arp: {}", err));
+            return out;
+        }
+        STATIC_DUE_TICKS.store(0, Ordering::Relaxed);
+        out.push(alloc::format!("net arp: {} -> {} (estatica)", ip_text, mac_text(&mac)));
+        let local = super::get_ip_address();
+        if let Some(IpAddress::Ipv4(local)) = local {
+            let prefix = unsafe { super::IFACE.get().as_ref() }
+                .and_then(local_ipv4)
+                .map(|(_, prefix)| prefix)
+                .unwrap_or(32);
+            if !same_subnet(ip, local.0, prefix) {
+                out.push(String::from(
+                    "net arp: aviso: fuera de la subred actual, se aplicara al cambiar de red",
+                ));
+            }
+        }
+        return out;
+    }
+
+    if sub.eq_ignore_ascii_case("del") && mac_arg.is_none() {
+        let Some(ip) = ip_arg.and_then(super::parse_ipv4_octets) else {
+            out.push(String::from("Uso: net arp del <ip>"));
+            return out;
+        };
+        match crate::config::unset(static_key(ip).as_str()) {
+            Ok(true) => {
+                // smoltcp would keep using the old binding until it expires.
+                FLUSH_PENDING.store(true, Ordering::Relaxed);
+                out.push(alloc::format!("net arp: {} borrada", NeighborIp::V4(ip).text()));
+            }
+            Ok(false) => out.push(alloc::format!(
+                "net arp: {} no es una entrada estatica",
+                NeighborIp::V4(ip).text()
+            )),
+            Err(err) => out.push(alloc::format!("net arp: {}", err)),
+        }
+        return out;
+    }
+
+    out.push(String::from("Uso: net arp [list|flush|add <ip> <mac>|del <ip>]"));
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "net_arp";
+
+    fn table_updates_and_evicts_oldest() {
+        let mut table = Vec::new();
+        let seen = |last: u8, mac_last: u8| NeighborObservation {
+            ip: NeighborIp::V4([10, 0, 0, last]),
+            mac: [2, 0, 0, 0, 0, mac_last],
+            source: NeighborSource::Arp,
+        };
+        record_into(&mut table, seen(1, 1), 10);
+        record_into(&mut table, seen(1, 2), 20);
+        crate::selftest::ensure_eq(table.len(), 1, "una fila por IP")?;
+        crate::selftest::ensure_eq((table[0].mac[5], table[0].seen_ticks), (2, 20), "MAC nueva")?;
+        for i in 0..NEIGHBOR_TABLE_MAX {
+            record_into(&mut table, seen(2 + i as u8, 0), 100 + i as u64);
+        }
+        crate::selftest::ensure_eq(table.len(), NEIGHBOR_TABLE_MAX, "tabla acotada")?;
+        crate::selftest::ensure(
+            table.iter().all(|entry| entry.ip != NeighborIp::V4([10, 0, 0, 1])),
+            "sale la mas antigua",
+        )
+    }
+
+    fn static_keys_and_subnets() {
+        let key = static_key([192, 168, 1, 1]);
+        crate::selftest::ensure_eq(key.as_str(), "net.arp.192-168-1-1", "clave")?;
+        crate::selftest::ensure(crate::config::valid_key(key.as_str()), "clave valida")?;
+        crate::selftest::ensure_eq(ip_from_static_key(key.as_str()), Some([192, 168, 1, 1]), "ida y vuelta")?;
+        crate::selftest::ensure(same_subnet([192, 168, 1, 1], [192, 168, 1, 50], 24), "misma /24")?;
+        crate::selftest::ensure(!same_subnet([192, 168, 2, 1], [192, 168, 1, 50], 24), "otra /24")?;
+        crate::selftest::ensure(same_subnet([8, 8, 8, 8], [10, 0, 0, 1], 0), "/0")
+    }
+}
@@ -0,0 +1,99 @@
+//! `net bench [url]`: download one resource through the normal HTTP client
+//! and report the throughput the stack reaches, together with the numbers
+//! that bound it (TCP receive buffer, frames received). The body is
+//! discarded; only its length counts.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub(crate) const DEFAULT_URL: &str = "http://speedtest.tele2.net/10MB.zip";
+/// 60 seconds at the 10 ms timer tick.
+const BENCH_TIMEOUT_MS: u64 = 60_000;
+
+/// (bytes per second, tenths of Mbit/s) for `bytes` moved in `elapsed_us`.
+fn throughput(bytes: u64, elapsed_us: u64) -> (u64, u64) {
+    let elapsed_us = elapsed_us.max(1);
+    let per_s = bytes.saturating_mul(1_000_000) / elapsed_us;
+    let mbit_tenths = bytes.saturating_mul(8 * 10) / elapsed_us;
+    (per_s, mbit_tenths)
+}
+
+/// Fetch `url` and drop the body: (body bytes, microseconds), or `None`
+/// when nothing came back.
+pub(crate) fn download(url: &str, pump_ui: &mut impl FnMut()) -> Option<(usize, u64)> {
+    let start = crate::perf::now_us();
+    let body = {
+        let _t = crate::trace::scope_with("net", "bench", url);
+        super::http_get_request_bytes_with_timeout(url, pump_ui, BENCH_TIMEOUT_MS)
+    }?;
+    Some((body.len(), crate::perf::now_us().saturating_sub(start)))
+}
+
+fn rx_frames() -> u64 {
+    crate::intel_net::RX_COUNT.load(core::sync::atomic::Ordering::Relaxed)
+}
+
+/// Shared implementation of `net bench [url]`.
+pub fn command_lines(args: &str, pump_ui: &mut impl FnMut()) -> Vec<String> {
+    let mut out = Vec::new();
+    let url = args.split_whitespace().next().unwrap_or(DEFAULT_URL);
+    let (rx_buf, tx_buf) = super::tcp_buffer_sizes();
+    out.push(alloc::format!("net bench: GET {}", url));
+    out.push(alloc::format!(
+        "  Buffers TCP: rx {}, tx {} ({} / {})",
+        crate::perf::format_bytes(rx_buf as u64),
+        crate::perf::format_bytes(tx_buf as u64),
+        super::TCP_RX_KIB_KEY,
+        super::TCP_TX_KIB_KEY
+    ));
+
+    let frames_before = rx_frames();
+    let (wire_before, _) = super::traffic_bytes();
+    let Some((len, elapsed)) = download(url, pump_ui) else {
+        out.push(String::from(
+            "  Sin respuesta (red caida, URL invalida o tiempo agotado).",
+        ));
+        return out;
+    };
+
+    let (wire_after, _) = super::traffic_bytes();
+    let wire = wire_after.saturating_sub(wire_before);
+    let (per_s, mbit_tenths) = throughput(len as u64, elapsed);
+    out.push(alloc::format!(
+        "  Recibidos {} en {}.{:03} s: {} ({}.{} Mbit/s)",
+        crate::perf::format_bytes(len as u64),
+        elapsed / 1_000_000,
+        (elapsed % 1_000_000) / 1000,
+        crate::perf::format_rate(per_s),
+        mbit_tenths / 10,
+        mbit_tenths % 10
+    ));
+    if crate::intel_net::get_model_name().is_some() {
+        out.push(alloc::format!(
+            "  Tramas RX: {} ({} en el cable, sin copia)",
+            rx_frames().saturating_sub(frames_before),
+            crate::perf::format_bytes(wire)
+        ));
+    } else {
+        out.push(alloc::format!("  En el cable: {}", crate::perf::format_bytes(wire)));
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "net_bench";
+
+    fn throughput_units() {
+        // 10 MiB in 2 s.
+        let (per_s, mbit_tenths) = throughput(10 * 1024 * 1024, 2_000_000);
+        crate::selftest::ensure_eq(per_s, 5 * 1024 * 1024, "bytes/s")?;
+        crate::selftest::ensure_eq(mbit_tenths, 419, "Mbit/s")?;
+        crate::selftest::ensure_eq(throughput(100, 0).0, 100_000_000, "sin division por cero")
+    }
+
+    fn tcp_buffer_sizes_are_clamped() {
+        crate::selftest::ensure_eq(crate::net::tcp_buffer_bytes(256), 256 * 1024, "por defecto")?;
+        crate::selftest::ensure_eq(crate::net::tcp_buffer_bytes(0), 4 * 1024, "minimo")?;
+        crate::selftest::ensure_eq(crate::net::tcp_buffer_bytes(1 << 20), 4096 * 1024, "maximo")
+    }
+}
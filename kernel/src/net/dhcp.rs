@@ -0,0 +1,425 @@
+//! DHCP lease bookkeeping on top of smoltcp's `dhcpv4::Socket`.
+//!
+//! The socket runs DISCOVER/REQUEST itself and renews at T1 and rebinds at
+//! T2 (options 58/59, else half and 7/8 of the lease). This module sets it
+//! up (host name in option 12, a request list that asks for the domain in
+//! option 15 and the lease timers) and gives it a receive buffer, which
+//! makes it report every ACK, renewals included, as a `Configured` event.
+//! Each ACK restarts the lease clock shown by `net` and is saved in
+//! `net.dhcp_lease`; at boot a saved lease that has not expired yet is
+//! applied right away, so the network is usable before the first exchange
+//! with the server completes.
+//!
+//! The socket repeats DISCOVER on its own, but can sit in REQUEST against a
+//! server that went away. While no lease is held a timer-wheel timer
+//! restarts the exchange from scratch, backing off from 15 s to 2 min.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+use redux_netparse::dhcp::{
+    dhcp_hostname, dhcp_lease_timers, parse_dhcp_ack_options, DhcpLeaseRecord, DHCP_OPT_DNS, DHCP_OPT_DOMAIN,
+    DHCP_OPT_HOSTNAME, DHCP_OPT_LEASE_TIME, DHCP_OPT_REBINDING_TIME, DHCP_OPT_RENEWAL_TIME, DHCP_OPT_ROUTER,
+    DHCP_OPT_SUBNET_MASK,
+};
+use smoltcp::iface::{Interface, SocketHandle, SocketSet};
+use smoltcp::socket::dhcpv4;
+use smoltcp::wire::{DhcpOption, IpAddress, IpCidr};
+
+use crate::println;
+use crate::spinlock::SpinLock;
+use crate::timer::{self, TimerId};
+
+pub const DHCP_LEASE_KEY: &str = "net.dhcp_lease";
+const HOSTNAME_KEY: &str = "system.hostname";
+const HOSTNAME_DEFAULT: &str = "redux";
+const DHCP_PACKET_BUFFER_BYTES: usize = 1500;
+/// A saved lease with less than this left is not worth applying.
+const DHCP_RESTORE_MIN_REMAINING_S: u64 = 60;
+/// Wall-clock times before this mean the clock was never set.
+const WALL_CLOCK_MIN_VALID_S: i64 = 1_600_000_000;
+const TICKS_PER_SECOND: u64 = 100;
+const DHCP_RETRY_FIRST_MS: u64 = 15_000;
+const DHCP_RETRY_MAX_MS: u64 = 120_000;
+static DHCP_PARAMETER_REQUEST_LIST: [u8; 7] = [
+    DHCP_OPT_SUBNET_MASK,
+    DHCP_OPT_ROUTER,
+    DHCP_OPT_DNS,
+    DHCP_OPT_DOMAIN,
+    DHCP_OPT_LEASE_TIME,
+    DHCP_OPT_RENEWAL_TIME,
+    DHCP_OPT_REBINDING_TIME,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LeasePhase {
+    Bound,
+    Renewing,
+    Rebinding,
+    Expired,
+}
+
+impl LeasePhase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LeasePhase::Bound => "activo",
+            LeasePhase::Renewing => "renovando (T1)",
+            LeasePhase::Rebinding => "rebind (T2)",
+            LeasePhase::Expired => "vencido",
+        }
+    }
+}
+
+struct LeaseState {
+    record: DhcpLeaseRecord,
+    t1_s: u32,
+    t2_s: u32,
+    /// Tick the lease clock was last anchored at, and how much of the lease
+    /// had already run by then (non-zero for a lease restored from disk).
+    base_ticks: u64,
+    base_elapsed_s: u64,
+    /// Applied from disk, not yet confirmed by the server.
+    restored: bool,
+}
+
+impl LeaseState {
+    fn elapsed_s(&self, now_ticks: u64) -> u64 {
+        self.base_elapsed_s + now_ticks.saturating_sub(self.base_ticks) / TICKS_PER_SECOND
+    }
+
+    fn phase(&self, now_ticks: u64) -> LeasePhase {
+        lease_phase(self.elapsed_s(now_ticks), self.t1_s, self.t2_s, self.record.lease_s)
+    }
+}
+
+static LEASE: SpinLock<Option<LeaseState>> = SpinLock::new(None);
+static HOSTNAME: SpinLock<Option<String>> = SpinLock::new(None);
+static RETRY_TIMER: AtomicU64 = AtomicU64::new(0);
+static RETRY_ATTEMPT: AtomicU32 = AtomicU32::new(0);
+static RETRY_DUE: AtomicBool = AtomicBool::new(false);
+
+fn lease_phase(elapsed_s: u64, t1_s: u32, t2_s: u32, lease_s: u32) -> LeasePhase {
+    if elapsed_s >= lease_s as u64 {
+        LeasePhase::Expired
+    } else if elapsed_s >= t2_s as u64 {
+        LeasePhase::Rebinding
+    } else if elapsed_s >= t1_s as u64 {
+        LeasePhase::Renewing
+    } else {
+        LeasePhase::Bound
+    }
+}
+
+fn wall_clock_unix_s() -> Option<i64> {
+    let now = crate::timer::wall_clock_unix_millis() / 1000;
+    (now >= WALL_CLOCK_MIN_VALID_S).then_some(now)
+}
+
+fn format_duration(seconds: u64) -> String {
+    if seconds >= 3600 {
+        alloc::format!("{}h {:02}m", seconds / 3600, (seconds % 3600) / 60)
+    } else {
+        alloc::format!("{}m {:02}s", seconds / 60, seconds % 60)
+    }
+}
+
+fn ip_text(ip: &[u8; 4]) -> String {
+    alloc::format!("{}.{}.{}.{}", ip[0], ip[1], ip[2], ip[3])
+}
+
+fn retry_delay_ms(attempt: u32) -> u64 {
+    DHCP_RETRY_FIRST_MS
+        .saturating_mul(1u64 << attempt.min(8))
+        .min(DHCP_RETRY_MAX_MS)
+}
+
+fn retry_fired(_: usize) {
+    RETRY_TIMER.store(0, Ordering::SeqCst);
+    RETRY_DUE.store(true, Ordering::SeqCst);
+}
+
+fn schedule_retry(attempt: u32) {
+    RETRY_ATTEMPT.store(attempt, Ordering::SeqCst);
+    let id = timer::after_ms(retry_delay_ms(attempt), retry_fired, 0);
+    if let Some(old) = TimerId::from_raw(RETRY_TIMER.swap(id.raw(), Ordering::SeqCst)) {
+        timer::cancel(old);
+    }
+}
+
+/// Discovery started: restart it if no lease shows up in time.
+pub fn arm_retry() {
+    RETRY_DUE.store(false, Ordering::SeqCst);
+    schedule_retry(0);
+}
+
+/// A lease arrived or DHCP is off.
+pub fn cancel_retry() {
+    RETRY_DUE.store(false, Ordering::SeqCst);
+    if let Some(id) = TimerId::from_raw(RETRY_TIMER.swap(0, Ordering::SeqCst)) {
+        timer::cancel(id);
+    }
+}
+
+/// Whether the retry timer fired since the last call; if so the next one is
+/// armed with a longer delay.
+pub fn take_retry_due() -> bool {
+    if !RETRY_DUE.swap(false, Ordering::SeqCst) {
+        return false;
+    }
+    schedule_retry(RETRY_ATTEMPT.load(Ordering::SeqCst).saturating_add(1));
+    true
+}
+
+/// Set up a new DHCP socket: host name, requested options and the receive
+/// buffer the lease bookkeeping reads ACKs from.
+pub fn configure_socket(socket: &mut dhcpv4::Socket<'static>) {
+    socket.set_parameter_request_list(&DHCP_PARAMETER_REQUEST_LIST);
+    let buffer = alloc::vec![0u8; DHCP_PACKET_BUFFER_BYTES];
+    socket.set_receive_packet_buffer(alloc::boxed::Box::leak(buffer.into_boxed_slice()));
+
+    let Some(hostname) = dhcp_hostname(crate::config::get_str(HOSTNAME_KEY, HOSTNAME_DEFAULT).as_str()) else {
+        return;
+    };
+    let data: &'static [u8] = alloc::boxed::Box::leak(hostname.clone().into_bytes().into_boxed_slice());
+    let options: &'static [DhcpOption<'static>] = alloc::boxed::Box::leak(
+        alloc::vec![DhcpOption {
+            kind: DHCP_OPT_HOSTNAME,
+            data,
+        }]
+        .into_boxed_slice(),
+    );
+    socket.set_outgoing_options(options);
+    *HOSTNAME.lock() = Some(hostname);
+}
+
+/// Apply the saved lease if it is still valid. Returns true when the
+/// interface got an address from it.
+pub fn restore_saved_lease(iface: &mut Interface, sockets: &mut SocketSet<'_>, dns_handle: SocketHandle) -> bool {
+    let Some(crate::config::ConfigValue::Str(text)) = crate::config::get(DHCP_LEASE_KEY) else {
+        return false;
+    };
+    let Some(record) = DhcpLeaseRecord::parse(text.as_str()) else {
+        return false;
+    };
+    let Some(now_unix) = wall_clock_unix_s() else {
+        return false;
+    };
+    let remaining = record.remaining_s(now_unix);
+    if remaining < DHCP_RESTORE_MIN_REMAINING_S {
+        return false;
+    }
+
+    let address = super::ipv4_from_octets(record.address);
+    iface.update_ip_addrs(|addrs| {
+        addrs.clear();
+        let _ = addrs.push(IpCidr::new(address.into(), record.prefix_len));
+    });
+    let _ = iface.routes_mut().remove_default_ipv4_route();
+    if let Some(router) = record.router {
+        let router = super::ipv4_from_octets(router);
+        if iface.routes_mut().add_default_ipv4_route(router).is_err() {
+            println("Net: DHCP Gateway route update failed.");
+        }
+        super::set_gateway(Some(router.into()));
+    }
+    let servers: Vec<IpAddress> = record
+        .dns
+        .iter()
+        .map(|ip| super::ipv4_from_octets(*ip).into())
+        .collect();
+    if !servers.is_empty() {
+        super::update_dns_servers(sockets, dns_handle, &servers);
+    }
+
+    println(
+        alloc::format!(
+            "Net: DHCP lease guardado -> {}/{} (quedan {})",
+            address,
+            record.prefix_len,
+            format_duration(remaining)
+        )
+        .as_str(),
+    );
+    super::arp::announce();
+    let (t1_s, t2_s) = dhcp_lease_timers(record.lease_s, None, None);
+    *LEASE.lock() = Some(LeaseState {
+        base_elapsed_s: record.lease_s as u64 - remaining,
+        record,
+        t1_s,
+        t2_s,
+        base_ticks: crate::timer::ticks(),
+        restored: true,
+    });
+    true
+}
+
+/// Record the lease of a `Configured` event (first ACK or a renewal).
+pub fn on_configured(config: &dhcpv4::Config<'_>, now_ticks: u64) {
+    let options = config
+        .packet
+        .as_ref()
+        .and_then(|packet| parse_dhcp_ack_options(packet.clone().into_inner()))
+        .unwrap_or_default();
+    let Some(lease_s) = options.lease_s else {
+        println("Net: DHCP ACK without lease time; lease not tracked.");
+        return;
+    };
+    let (t1_s, t2_s) = dhcp_lease_timers(lease_s, options.renew_s, options.rebind_s);
+    let record = DhcpLeaseRecord {
+        address: config.address.address().0,
+        prefix_len: config.address.prefix_len(),
+        router: config.router.map(|router| router.0),
+        dns: config.dns_servers.iter().map(|server| server.0).collect(),
+        server: options.server_id.or(Some(config.server.identifier.0)),
+        domain: options.domain,
+        acquired_unix_s: wall_clock_unix_s().unwrap_or(0),
+        lease_s,
+    };
+
+    let renewed = LEASE
+        .lock()
+        .as_ref()
+        .map(|lease| !lease.restored && lease.record.address == record.address)
+        .unwrap_or(false);
+    if renewed {
+        println(alloc::format!("Net: DHCP lease renewed ({}).", format_duration(lease_s as u64)).as_str());
+    } else {
+        println(
+            alloc::format!(
+                "Net: DHCP lease {} s (T1 {} s, T2 {} s){}",
+                lease_s,
+                t1_s,
+                t2_s,
+                record
+                    .domain
+                    .as_deref()
+                    .map(|domain| alloc::format!(", dominio {}", domain))
+                    .unwrap_or_default()
+            )
+            .as_str(),
+        );
+    }
+
+    // Without a wall clock the lease cannot be checked after a reboot.
+    if record.acquired_unix_s != 0 {
+        let _ = crate::config::set(DHCP_LEASE_KEY, crate::config::ConfigValue::Str(record.to_text()));
+    }
+    *LEASE.lock() = Some(LeaseState {
+        record,
+        t1_s,
+        t2_s,
+        base_ticks: now_ticks,
+        base_elapsed_s: 0,
+        restored: false,
+    });
+}
+
+/// The socket dropped its lease (expired, NAK or reset). The saved copy is
+/// only forgotten once it has expired; a reset keeps it for the next boot.
+pub fn on_deconfigured(now_ticks: u64) {
+    let expired = LEASE
+        .lock()
+        .take()
+        .map(|lease| lease.phase(now_ticks) == LeasePhase::Expired)
+        .unwrap_or(false);
+    if expired {
+        let _ = crate::config::unset(DHCP_LEASE_KEY);
+    }
+}
+
+/// Whether a lease is held and has not run out.
+pub fn has_lease(now_ticks: u64) -> bool {
+    LEASE
+        .lock()
+        .as_ref()
+        .map(|lease| lease.phase(now_ticks) != LeasePhase::Expired)
+        .unwrap_or(false)
+}
+
+/// The DHCP domain (option 15), if the server sent one.
+pub fn domain() -> Option<String> {
+    LEASE.lock().as_ref().and_then(|lease| lease.record.domain.clone())
+}
+
+/// DNS servers of the held lease.
+pub fn lease_dns() -> Vec<[u8; 4]> {
+    LEASE.lock().as_ref().map(|lease| lease.record.dns.clone()).unwrap_or_default()
+}
+
+/// `host` with the DHCP domain appended when it is a single label, the way
+/// a resolver search list does (`nas` -> `nas.home.lan`).
+pub fn qualify_host(host: &str) -> String {
+    if host.is_empty() || host.contains('.') || host.contains(':') || host.eq_ignore_ascii_case("localhost") {
+        return String::from(host);
+    }
+    match domain() {
+        Some(domain) => alloc::format!("{}.{}", host, domain),
+        None => String::from(host),
+    }
+}
+
+/// Lease lines for `net`.
+pub fn status_lines() -> Vec<String> {
+    let mut out = Vec::new();
+    let now = crate::timer::ticks();
+    if let Some(lease) = LEASE.lock().as_ref() {
+        let elapsed = lease.elapsed_s(now);
+        let lease_s = lease.record.lease_s as u64;
+        let phase = lease.phase(now);
+        out.push(alloc::format!(
+            "Net: lease DHCP -> {}/{}{}, {}{}",
+            ip_text(&lease.record.address),
+            lease.record.prefix_len,
+            lease
+                .record
+                .server
+                .as_ref()
+                .map(|server| alloc::format!(" de {}", ip_text(server)))
+                .unwrap_or_default(),
+            phase.as_str(),
+            if lease.restored { " (guardado)" } else { "" }
+        ));
+        if phase == LeasePhase::Expired {
+            out.push(String::from("Net: lease vencido, esperando al servidor DHCP"));
+        } else {
+            let until = |at_s: u64| format_duration(at_s.saturating_sub(elapsed));
+            out.push(alloc::format!(
+                "Net: vence en {} (renovacion en {}, rebind en {}, duracion {})",
+                until(lease_s),
+                until(lease.t1_s as u64),
+                until(lease.t2_s as u64),
+                format_duration(lease_s)
+            ));
+        }
+        if let Some(domain) = lease.record.domain.as_deref() {
+            out.push(alloc::format!("Net: dominio DHCP -> {}", domain));
+        }
+    }
+    if let Some(hostname) = HOSTNAME.lock().as_deref() {
+        out.push(alloc::format!("Net: hostname DHCP -> {} ({})", hostname, HOSTNAME_KEY));
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "net_dhcp";
+
+    fn lease_phases_follow_t1_t2() {
+        crate::selftest::ensure_eq(lease_phase(0, 1800, 3150, 3600), LeasePhase::Bound, "recien obtenido")?;
+        crate::selftest::ensure_eq(lease_phase(1800, 1800, 3150, 3600), LeasePhase::Renewing, "en T1")?;
+        crate::selftest::ensure_eq(lease_phase(3200, 1800, 3150, 3600), LeasePhase::Rebinding, "pasado T2")?;
+        crate::selftest::ensure_eq(lease_phase(3600, 1800, 3150, 3600), LeasePhase::Expired, "vencido")
+    }
+
+    fn retry_backs_off_to_the_cap() {
+        crate::selftest::ensure_eq(retry_delay_ms(0), DHCP_RETRY_FIRST_MS, "primero")?;
+        crate::selftest::ensure_eq(retry_delay_ms(1), 2 * DHCP_RETRY_FIRST_MS, "segundo")?;
+        crate::selftest::ensure_eq(retry_delay_ms(40), DHCP_RETRY_MAX_MS, "tope")
+    }
+
+    fn durations_are_compact() {
+        crate::selftest::ensure_eq(format_duration(59).as_str(), "0m 59s", "segundos")?;
+        crate::selftest::ensure_eq(format_duration(3_725).as_str(), "1h 02m", "horas")
+    }
+}
@@ -0,0 +1,151 @@
+//! Outbound host filter for userspace HTTP (`redux_http` syscalls and ReduxLang).
+//!
+//! Rules are checked in order and the first match wins; without a match the
+//! default policy applies. Kernel-internal fetches (browser, updater) are not
+//! filtered here.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::spinlock::SpinLock;
+
+const FIREWALL_MAX_RULES: usize = 32;
+
+#[derive(Clone)]
+pub struct FirewallRule {
+    /// `*`, `*.example.com` or an exact host, compared case-insensitively.
+    pub host: String,
+    pub port: Option<u16>,
+    pub allow: bool,
+}
+
+static FIREWALL_RULES: SpinLock<Vec<FirewallRule>> = SpinLock::new(Vec::new());
+static FIREWALL_DEFAULT_ALLOW: AtomicBool = AtomicBool::new(true);
+
+fn host_matches(pattern: &str, host: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+    if let Some(suffix) = pattern.strip_prefix("*.") {
+        if host.eq_ignore_ascii_case(suffix) {
+            return true;
+        }
+        return host.len() > suffix.len() + 1
+            && host.as_bytes()[host.len() - suffix.len() - 1] == b'.'
+            && host[host.len() - suffix.len()..].eq_ignore_ascii_case(suffix);
+    }
+    pattern.eq_ignore_ascii_case(host)
+}
+
+/// True when userspace may connect to `host:port`.
+pub fn allows(host: &str, port: u16) -> bool {
+    for rule in FIREWALL_RULES.lock().iter() {
+        if rule.port.map(|p| p == port).unwrap_or(true) && host_matches(rule.host.as_str(), host) {
+            return rule.allow;
+        }
+    }
+    FIREWALL_DEFAULT_ALLOW.load(Ordering::Relaxed)
+}
+
+pub fn add_rule(host: &str, port: Option<u16>, allow: bool) -> Result<(), &'static str> {
+    let host = host.trim();
+    if host.is_empty() || host.contains(char::is_whitespace) {
+        return Err("Host invalido.");
+    }
+    let mut rules = FIREWALL_RULES.lock();
+    if rules.len() >= FIREWALL_MAX_RULES {
+        return Err("Tabla de reglas llena.");
+    }
+    rules.push(FirewallRule { host: String::from(host), port, allow });
+    Ok(())
+}
+
+pub fn remove_rule(index: usize) -> bool {
+    let mut rules = FIREWALL_RULES.lock();
+    if index < rules.len() {
+        rules.remove(index);
+        true
+    } else {
+        false
+    }
+}
+
+pub fn set_default_allow(allow: bool) {
+    FIREWALL_DEFAULT_ALLOW.store(allow, Ordering::Relaxed);
+}
+
+fn parse_target(text: &str) -> Result<(&str, Option<u16>), &'static str> {
+    match text.rsplit_once(':') {
+        Some((host, port)) => port.parse::<u16>().map(|p| (host, Some(p))).map_err(|_| "Puerto invalido."),
+        None => Ok((text, None)),
+    }
+}
+
+fn rule_line(index: usize, rule: &FirewallRule) -> String {
+    match rule.port {
+        Some(port) => alloc::format!(
+            "  {}: {} {}:{}",
+            index,
+            if rule.allow { "allow" } else { "deny " },
+            rule.host,
+            port
+        ),
+        None => alloc::format!("  {}: {} {}", index, if rule.allow { "allow" } else { "deny " }, rule.host),
+    }
+}
+
+/// Shared implementation of `firewall [list|allow|deny|rm|policy|check]`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut parts = args.split_whitespace();
+    let sub = parts.next().unwrap_or("list");
+    let arg = parts.next();
+    let mut out = Vec::new();
+
+    match (sub, arg) {
+        ("list", _) => {
+            out.push(alloc::format!(
+                "Firewall (userspace HTTP): politica por defecto = {}",
+                if FIREWALL_DEFAULT_ALLOW.load(Ordering::Relaxed) { "allow" } else { "deny" }
+            ));
+            let rules = FIREWALL_RULES.lock();
+            for (i, rule) in rules.iter().enumerate() {
+                out.push(rule_line(i, rule));
+            }
+            if rules.is_empty() {
+                out.push(String::from("  (sin reglas)"));
+            }
+        }
+        ("allow", Some(target)) | ("deny", Some(target)) => {
+            let result = parse_target(target).and_then(|(host, port)| add_rule(host, port, sub == "allow"));
+            match result {
+                Ok(()) => out.push(alloc::format!("Regla agregada: {} {}", sub, target)),
+                Err(e) => out.push(String::from(e)),
+            }
+        }
+        ("rm", Some(index)) => match index.parse::<usize>() {
+            Ok(i) if remove_rule(i) => out.push(alloc::format!("Regla {} eliminada.", i)),
+            _ => out.push(String::from("Indice de regla invalido.")),
+        },
+        ("policy", Some("allow")) | ("policy", Some("deny")) => {
+            set_default_allow(arg == Some("allow"));
+            out.push(alloc::format!("Politica por defecto: {}", arg.unwrap_or("")));
+        }
+        ("check", Some(target)) => match parse_target(target) {
+            Ok((host, port)) => {
+                let port = port.unwrap_or(80);
+                out.push(alloc::format!(
+                    "{}:{} -> {}",
+                    host,
+                    port,
+                    if allows(host, port) { "permitido" } else { "bloqueado" }
+                ));
+            }
+            Err(e) => out.push(String::from(e)),
+        },
+        _ => out.push(String::from(
+            "Uso: firewall [list|allow <host>[:puerto]|deny <host>[:puerto]|rm <n>|policy allow|deny|check <host>[:puerto]]",
+        )),
+    }
+    out
+}
@@ -0,0 +1,507 @@
+//! FTP / FTPS client behind the `ftp` command (shell, GUI terminal and the
+//! Explorer "Conectar a servidor" prompt).
+//!
+//! One session at a time. Transfers use passive mode (EPSV, then PASV) and
+//! the data connection always goes to the control connection's address,
+//! whatever PASV reports. `ftps://` is explicit TLS: `AUTH TLS` on the normal
+//! port, then `PBSZ 0` / `PROT P` so listings and files are encrypted too.
+//! Control and data channels share one rustls config, so data channels can
+//! resume the control session as most FTPS servers require.
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use rustls::ClientConfig;
+use smoltcp::iface::{Interface, SocketHandle, SocketSet};
+use smoltcp::socket::tcp;
+use smoltcp::wire::Ipv4Address;
+
+use redux_netparse::ftp::{
+    ftp_arg_is_safe, parse_epsv_reply, parse_ftp_reply, parse_ftp_target, parse_list, parse_pasv_reply,
+    parse_pwd_reply, FtpReply,
+};
+
+use super::tls::{self, TlsConnection};
+use super::{IFACE, NET_BLOCKING_LOOP_STALL_US, SOCKETS, TLS_MAX_RECORD_PLAINTEXT};
+use crate::sync::KernelCell;
+
+const FTP_TIMEOUT_TICKS: u64 = 3_000;
+const FTP_MAX_TRANSFER_BYTES: usize = 16 * 1024 * 1024;
+const FTP_MAX_REPLY_BYTES: usize = 16 * 1024;
+const FTP_ANONYMOUS_USER: &str = "anonymous";
+const FTP_ANONYMOUS_PASSWORD: &str = "redux@";
+
+struct FtpSession {
+    host: String,
+    port: u16,
+    addr: Ipv4Address,
+    user: String,
+    control: SocketHandle,
+    tls: Option<TlsConnection>,
+    tls_config: Option<Arc<ClientConfig>>,
+    rx: Vec<u8>,
+    cwd: String,
+}
+
+static FTP_SESSION: KernelCell<Option<FtpSession>> = KernelCell::new(None);
+
+/// The smoltcp interface and socket set.
+///
+/// # Safety
+///
+/// Kernel thread only, and nothing else may use `IFACE` or `SOCKETS` while
+/// the borrows are alive: the `pump_ui` callbacks passed down here redraw
+/// the desktop but must never reach `net::poll`.
+unsafe fn stack() -> Result<(&'static mut Interface, &'static mut SocketSet<'static>), String> {
+    unsafe {
+        match (IFACE.get_mut().as_mut(), SOCKETS.get_mut().as_mut()) {
+            (Some(iface), Some(sockets)) => Ok((iface, sockets)),
+            _ => Err(String::from("la red no esta inicializada")),
+        }
+    }
+}
+
+/// The open session.
+///
+/// # Safety
+///
+/// Kernel thread only, one borrow in use at a time, and none may be held
+/// across `connect` or `close`, which replace the session.
+unsafe fn session() -> Result<&'static mut FtpSession, String> {
+    unsafe { FTP_SESSION.get_mut().as_mut().ok_or_else(|| String::from("sin sesion FTP (usa: ftp open <host>)")) }
+}
+
+fn reply_error(reply: &FtpReply) -> String {
+    alloc::format!("{} {}", reply.code, reply.text.lines().last().unwrap_or(""))
+}
+
+fn send_all(
+    handle: SocketHandle,
+    tls: Option<&mut TlsConnection>,
+    data: &[u8],
+    pump_ui: &mut impl FnMut(),
+) -> Result<(), String> {
+    // SAFETY: kernel thread; this borrow is dead before anything else takes the stack.
+    let (iface, sockets) = unsafe { stack() }?;
+    super::tcp_send_all_blocking(iface, sockets, handle, tls, data, pump_ui, FTP_TIMEOUT_TICKS).map_err(String::from)
+}
+
+fn read_reply(session: &mut FtpSession, pump_ui: &mut impl FnMut()) -> Result<FtpReply, String> {
+    // SAFETY: kernel thread; this borrow is dead before anything else takes the stack.
+    let (iface, sockets) = unsafe { stack() }?;
+    let mut buf = alloc::vec![0u8; TLS_MAX_RECORD_PLAINTEXT];
+    let mut last_data = crate::timer::ticks();
+    loop {
+        if let Some(reply) = parse_ftp_reply(session.rx.as_slice()) {
+            session.rx.drain(..reply.consumed);
+            return Ok(reply);
+        }
+        if session.rx.len() > FTP_MAX_REPLY_BYTES {
+            return Err(String::from("respuesta del servidor demasiado larga"));
+        }
+
+        pump_ui();
+        super::net_poll_blocking(iface, sockets);
+        let socket = sockets.get_mut::<tcp::Socket>(session.control);
+        let len = match session.tls.as_mut() {
+            Some(tls) => tls.read(socket, &mut buf),
+            None => socket.recv_slice(&mut buf).unwrap_or(0),
+        };
+        if len > 0 {
+            session.rx.extend_from_slice(&buf[..len]);
+            last_data = crate::timer::ticks();
+            continue;
+        }
+        if !socket.may_recv() {
+            return Err(String::from("conexion cerrada por el servidor"));
+        }
+        if crate::timer::ticks() - last_data > FTP_TIMEOUT_TICKS {
+            return Err(String::from("timeout esperando respuesta"));
+        }
+        uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
+    }
+}
+
+fn command(session: &mut FtpSession, line: &str, pump_ui: &mut impl FnMut()) -> Result<FtpReply, String> {
+    if !ftp_arg_is_safe(line) {
+        return Err(String::from("argumento invalido"));
+    }
+    let request = alloc::format!("{}\r\n", line);
+    send_all(session.control, session.tls.as_mut(), request.as_bytes(), pump_ui)?;
+    read_reply(session, pump_ui)
+}
+
+fn expect_class(reply: FtpReply, class: u16) -> Result<FtpReply, String> {
+    if reply.class() == class {
+        Ok(reply)
+    } else {
+        Err(reply_error(&reply))
+    }
+}
+
+fn close_socket(handle: SocketHandle, pump_ui: &mut impl FnMut()) {
+    // SAFETY: kernel thread; the borrow ends with the block.
+    if let Ok((iface, sockets)) = unsafe { stack() } {
+        super::tcp_close_blocking(iface, sockets, handle, pump_ui, FTP_TIMEOUT_TICKS);
+    }
+}
+
+fn login(
+    session: &mut FtpSession,
+    secure: bool,
+    user: &str,
+    password: &str,
+    pump_ui: &mut impl FnMut(),
+) -> Result<String, String> {
+    let greeting = expect_class(read_reply(session, pump_ui)?, 2)?;
+
+    if secure {
+        let reply = command(session, "AUTH TLS", pump_ui)?;
+        if reply.code != 234 {
+            return Err(alloc::format!("el servidor no admite AUTH TLS ({})", reply_error(&reply)));
+        }
+        let config = Arc::new(tls::webpki_client_config());
+        let mut conn = TlsConnection::new_shared(session.host.as_str(), &config)
+            .ok_or_else(|| String::from("no se pudo iniciar TLS"))?;
+        // SAFETY: kernel thread; this borrow is dead before anything else takes the stack.
+        let (iface, sockets) = unsafe { stack() }?;
+        if !super::tls_handshake_blocking(iface, sockets, session.control, &mut conn, pump_ui, FTP_TIMEOUT_TICKS) {
+            return Err(String::from("fallo el handshake TLS del canal de control"));
+        }
+        session.tls = Some(conn);
+        session.tls_config = Some(config);
+        expect_class(command(session, "PBSZ 0", pump_ui)?, 2)?;
+        expect_class(command(session, "PROT P", pump_ui)?, 2)?;
+    }
+
+    let reply = command(session, alloc::format!("USER {}", user).as_str(), pump_ui)?;
+    match reply.code {
+        230 => {}
+        331 | 332 => {
+            let reply = command(session, alloc::format!("PASS {}", password).as_str(), pump_ui)?;
+            if reply.class() != 2 {
+                return Err(alloc::format!("login rechazado: {}", reply_error(&reply)));
+            }
+        }
+        _ => return Err(alloc::format!("login rechazado: {}", reply_error(&reply))),
+    }
+    expect_class(command(session, "TYPE I", pump_ui)?, 2)?;
+    Ok(String::from(greeting.text.lines().next().unwrap_or("")))
+}
+
+/// Open a session to `target` (`ftp://`, `ftps://` or `host[:port]`),
+/// replacing any previous one. Returns the lines to show the user.
+pub fn connect(target: &str, pump_ui: &mut impl FnMut()) -> Result<Vec<String>, String> {
+    let target = parse_ftp_target(target).ok_or_else(|| String::from("destino FTP invalido"))?;
+    close(pump_ui);
+
+    // SAFETY: kernel thread; this borrow is dead before anything else takes the stack.
+    let (iface, sockets) = unsafe { stack() }?;
+    let addr = super::resolve_ipv4_blocking(iface, sockets, target.host.as_str(), pump_ui, FTP_TIMEOUT_TICKS)
+        .ok_or_else(|| alloc::format!("no se pudo resolver {}", target.host))?;
+    let control = super::tcp_connect_blocking(iface, sockets, addr, target.port, pump_ui, FTP_TIMEOUT_TICKS)
+        .ok_or_else(|| alloc::format!("no se pudo conectar a {}:{}", target.host, target.port))?;
+
+    let user = target.user.clone().unwrap_or_else(|| String::from(FTP_ANONYMOUS_USER));
+    let password = match target.password.as_deref() {
+        Some(password) => String::from(password),
+        None if user == FTP_ANONYMOUS_USER => String::from(FTP_ANONYMOUS_PASSWORD),
+        None => String::new(),
+    };
+    let mut session = FtpSession {
+        host: target.host.clone(),
+        port: target.port,
+        addr,
+        user: user.clone(),
+        control,
+        tls: None,
+        tls_config: None,
+        rx: Vec::new(),
+        cwd: String::from("/"),
+    };
+
+    let greeting = match login(&mut session, target.secure, user.as_str(), password.as_str(), pump_ui) {
+        Ok(greeting) => greeting,
+        Err(err) => {
+            close_socket(control, pump_ui);
+            return Err(err);
+        }
+    };
+
+    let mut out = Vec::new();
+    out.push(alloc::format!("FTP: {}", greeting));
+    if !target.secure && target.password.is_some() {
+        out.push(String::from("Aviso: FTP sin cifrar, la clave viaja en claro (usa ftps://)."));
+    }
+    if !target.path.is_empty() {
+        if let Err(err) = command(&mut session, alloc::format!("CWD {}", target.path).as_str(), pump_ui)
+            .and_then(|r| expect_class(r, 2))
+        {
+            out.push(alloc::format!("CWD {}: {}", target.path, err));
+        }
+    }
+    if let Ok(reply) = command(&mut session, "PWD", pump_ui) {
+        if let Some(path) = parse_pwd_reply(reply.text.as_str()) {
+            session.cwd = path;
+        }
+    }
+    out.push(alloc::format!(
+        "Conectado a {}:{} como {}{} - {}",
+        session.host,
+        session.port,
+        session.user,
+        if target.secure { " (FTPS)" } else { "" },
+        session.cwd
+    ));
+    unsafe {
+        *FTP_SESSION.get_mut() = Some(session);
+    }
+    Ok(out)
+}
+
+/// Send `QUIT` and drop the session, if any.
+pub fn close(pump_ui: &mut impl FnMut()) {
+    let Some(mut session) = (unsafe { FTP_SESSION.get_mut().take() }) else {
+        return;
+    };
+    let _ = command(&mut session, "QUIT", pump_ui);
+    close_socket(session.control, pump_ui);
+}
+
+fn open_data(session: &mut FtpSession, pump_ui: &mut impl FnMut()) -> Result<SocketHandle, String> {
+    let port = match command(session, "EPSV", pump_ui) {
+        Ok(reply) if reply.code == 229 => parse_epsv_reply(reply.text.as_str()),
+        _ => None,
+    };
+    let port = match port {
+        Some(port) => port,
+        None => {
+            let reply = expect_class(command(session, "PASV", pump_ui)?, 2)?;
+            // Only the port is used: connecting to the address PASV reports
+            // would let a server bounce us to a third host.
+            parse_pasv_reply(reply.text.as_str())
+                .map(|(_, port)| port)
+                .ok_or_else(|| String::from("respuesta PASV invalida"))?
+        }
+    };
+    // SAFETY: kernel thread; this borrow is dead before anything else takes the stack.
+    let (iface, sockets) = unsafe { stack() }?;
+    super::tcp_connect_blocking(iface, sockets, session.addr, port, pump_ui, FTP_TIMEOUT_TICKS)
+        .ok_or_else(|| String::from("no se pudo abrir la conexion de datos"))
+}
+
+/// TLS on the data connection when the session is FTPS (`PROT P`).
+fn secure_data(
+    session: &FtpSession,
+    data: SocketHandle,
+    pump_ui: &mut impl FnMut(),
+) -> Result<Option<TlsConnection>, String> {
+    let Some(config) = session.tls_config.as_ref() else {
+        return Ok(None);
+    };
+    let mut conn = TlsConnection::new_shared(session.host.as_str(), config)
+        .ok_or_else(|| String::from("no se pudo iniciar TLS"))?;
+    // SAFETY: kernel thread; this borrow is dead before anything else takes the stack.
+    let (iface, sockets) = unsafe { stack() }?;
+    if !super::tls_handshake_blocking(iface, sockets, data, &mut conn, pump_ui, FTP_TIMEOUT_TICKS) {
+        return Err(String::from("fallo el handshake TLS del canal de datos"));
+    }
+    Ok(Some(conn))
+}
+
+/// Run a command that sends data to us (`LIST`, `RETR`).
+fn transfer_in(session: &mut FtpSession, line: &str, pump_ui: &mut impl FnMut()) -> Result<Vec<u8>, String> {
+    let data = open_data(session, pump_ui)?;
+    if let Err(err) = command(session, line, pump_ui).and_then(|r| expect_class(r, 1)) {
+        close_socket(data, pump_ui);
+        return Err(err);
+    }
+    let mut data_tls = match secure_data(session, data, pump_ui) {
+        Ok(conn) => conn,
+        Err(err) => {
+            close_socket(data, pump_ui);
+            let _ = read_reply(session, pump_ui);
+            return Err(err);
+        }
+    };
+
+    // SAFETY: kernel thread; this borrow is dead before anything else takes the stack.
+    let (iface, sockets) = unsafe { stack() }?;
+    let bytes = super::tcp_read_until_close(
+        iface,
+        sockets,
+        data,
+        data_tls.as_mut(),
+        pump_ui,
+        FTP_TIMEOUT_TICKS,
+        FTP_MAX_TRANSFER_BYTES,
+    );
+    close_socket(data, pump_ui);
+    let done = read_reply(session, pump_ui)?;
+    if bytes.len() >= FTP_MAX_TRANSFER_BYTES {
+        return Err(alloc::format!(
+            "el archivo supera el limite de {} MiB",
+            FTP_MAX_TRANSFER_BYTES / (1024 * 1024)
+        ));
+    }
+    expect_class(done, 2)?;
+    Ok(bytes)
+}
+
+/// Run a command that receives data from us (`STOR`).
+fn transfer_out(
+    session: &mut FtpSession,
+    line: &str,
+    payload: &[u8],
+    pump_ui: &mut impl FnMut(),
+) -> Result<(), String> {
+    let data = open_data(session, pump_ui)?;
+    if let Err(err) = command(session, line, pump_ui).and_then(|r| expect_class(r, 1)) {
+        close_socket(data, pump_ui);
+        return Err(err);
+    }
+    let sent = secure_data(session, data, pump_ui)
+        .and_then(|mut conn| send_all(data, conn.as_mut(), payload, pump_ui));
+    // The server only sees the end of the file when the data connection closes.
+    close_socket(data, pump_ui);
+    let done = read_reply(session, pump_ui)?;
+    sent?;
+    expect_class(done, 2).map(|_| ())
+}
+
+/// `LIST` of `path` (or the current directory) as display lines.
+pub fn list(path: Option<&str>, pump_ui: &mut impl FnMut()) -> Result<Vec<String>, String> {
+    // SAFETY: kernel thread; nothing below opens or closes the session.
+    let session = unsafe { session() }?;
+    let line = match path {
+        Some(path) => alloc::format!("LIST {}", path),
+        None => String::from("LIST"),
+    };
+    let raw = transfer_in(session, line.as_str(), pump_ui)?;
+    let text = String::from_utf8_lossy(raw.as_slice());
+
+    let mut out = Vec::new();
+    out.push(alloc::format!("{}:{}", session.host, path.unwrap_or(session.cwd.as_str())));
+    let entries = parse_list(text.as_ref());
+    if entries.is_empty() {
+        // Unknown listing format: show it as the server sent it.
+        out.extend(text.lines().filter(|l| !l.trim().is_empty()).map(|l| alloc::format!("  {}", l)));
+    }
+    for entry in entries.iter() {
+        let kind = if entry.is_dir {
+            "DIR "
+        } else if entry.is_link {
+            "LINK"
+        } else {
+            "FILE"
+        };
+        match entry.size {
+            Some(size) => out.push(alloc::format!("  [{}] {} ({} bytes)", kind, entry.name, size)),
+            None => out.push(alloc::format!("  [{}] {}", kind, entry.name)),
+        }
+    }
+    if out.len() == 1 {
+        out.push(String::from("  (vacio)"));
+    }
+    Ok(out)
+}
+
+pub fn change_dir(path: &str, pump_ui: &mut impl FnMut()) -> Result<String, String> {
+    // SAFETY: kernel thread; nothing below opens or closes the session.
+    let session = unsafe { session() }?;
+    expect_class(command(session, alloc::format!("CWD {}", path).as_str(), pump_ui)?, 2)?;
+    let reply = command(session, "PWD", pump_ui)?;
+    session.cwd = parse_pwd_reply(reply.text.as_str()).unwrap_or_else(|| String::from(path));
+    Ok(session.cwd.clone())
+}
+
+pub fn retrieve(path: &str, pump_ui: &mut impl FnMut()) -> Result<Vec<u8>, String> {
+    // SAFETY: kernel thread; nothing below opens or closes the session.
+    let session = unsafe { session() }?;
+    transfer_in(session, alloc::format!("RETR {}", path).as_str(), pump_ui)
+}
+
+pub fn store(path: &str, payload: &[u8], pump_ui: &mut impl FnMut()) -> Result<(), String> {
+    // SAFETY: kernel thread; nothing below opens or closes the session.
+    let session = unsafe { session() }?;
+    transfer_out(session, alloc::format!("STOR {}", path).as_str(), payload, pump_ui)
+}
+
+pub fn status_line() -> String {
+    match unsafe { FTP_SESSION.get() } {
+        Some(s) => alloc::format!(
+            "FTP: {}@{}:{}{} - {}",
+            s.user,
+            s.host,
+            s.port,
+            if s.tls.is_some() { " (FTPS)" } else { "" },
+            s.cwd
+        ),
+        None => String::from("FTP: sin sesion"),
+    }
+}
+
+fn remote_base_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Shared implementation of the `ftp` command. Local files are read from and
+/// written to the FAT directory `dir_cluster`.
+pub fn command_lines(args: &str, dir_cluster: u32, pump_ui: &mut impl FnMut()) -> Vec<String> {
+    let mut parts = args.split_whitespace();
+    let sub = parts.next().unwrap_or("status");
+    let a = parts.next();
+    let b = parts.next();
+    let mut out = Vec::new();
+
+    match (sub, a, b) {
+        ("status", _, _) => out.push(status_line()),
+        ("open", Some(target), _) => match connect(target, pump_ui) {
+            Ok(lines) => out.extend(lines),
+            Err(e) => out.push(alloc::format!("FTP: {}", e)),
+        },
+        ("ls", path, _) => match list(path, pump_ui) {
+            Ok(lines) => out.extend(lines),
+            Err(e) => out.push(alloc::format!("FTP: {}", e)),
+        },
+        ("cd", Some(path), _) => match change_dir(path, pump_ui) {
+            Ok(cwd) => out.push(alloc::format!("FTP: {}", cwd)),
+            Err(e) => out.push(alloc::format!("FTP: {}", e)),
+        },
+        ("pwd", _, _) => out.push(status_line()),
+        ("get", Some(src), dst) => {
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+            let name = dst.unwrap_or_else(|| remote_base_name(src));
+            let result = retrieve(src, pump_ui).and_then(|data| {
+                fat.write_text_file_in_dir(dir_cluster, name, &data)
+                    .map(|_| data.len())
+                    .map_err(String::from)
+            });
+            match result {
+                Ok(len) => out.push(alloc::format!("{} -> {} ({} bytes)", src, name, len)),
+                Err(e) => out.push(alloc::format!("FTP: {}", e)),
+            }
+        }
+        ("put", Some(src), dst) => {
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+            let target = dst.unwrap_or(src);
+            let result = fat
+                .read_file_in_dir(dir_cluster, src)
+                .map_err(String::from)
+                .and_then(|data| store(target, &data, pump_ui).map(|_| data.len()));
+            match result {
+                Ok(len) => out.push(alloc::format!("{} -> {} ({} bytes)", src, target, len)),
+                Err(e) => out.push(alloc::format!("FTP: {}", e)),
+            }
+        }
+        ("close", _, _) => {
+            close(pump_ui);
+            out.push(String::from("FTP: sesion cerrada."));
+        }
+        _ => out.push(String::from(
+            "Uso: ftp [status|open <ftp[s]://[usuario[:clave]@]host[:puerto][/ruta]>|ls [ruta]|cd <ruta>|pwd|get <remoto> [archivo]|put <archivo> [remoto]|close]",
+        )),
+    }
+    out
+}
@@ -0,0 +1,223 @@
+//! Gemini client (`gemini://`).
+//!
+//! Capsules almost always use self-signed certificates, so the webpki roots
+//! are not used: the first certificate seen for a host is pinned (TOFU) and
+//! later connections must present the same one. Pins live in memory until
+//! reboot; `gemini forget <host>` drops one after a legitimate key change.
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, WebPkiSupportedAlgorithms};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, DigitallySignedStruct, Error, SignatureScheme};
+use sha2::{Digest, Sha256};
+
+use redux_netparse::gemini::{parse_gemini_header, parse_gemini_url};
+use redux_netparse::starts_with_ignore_ascii_case;
+use redux_netparse::url::resolve_reference;
+
+use crate::spinlock::SpinLock;
+
+const GEMINI_MAX_REDIRECTS: usize = 5;
+const GEMINI_MAX_RESPONSE_BYTES: usize = 2 * 1024 * 1024;
+const GEMINI_TIMEOUT_TICKS: u64 = 3_000;
+const GEMINI_MAX_PINS: usize = 64;
+
+struct PinnedHost {
+    host: String,
+    fingerprint: [u8; 32],
+}
+
+static GEMINI_PINS: SpinLock<Vec<PinnedHost>> = SpinLock::new(Vec::new());
+// Fingerprint seen during the current handshake; pinned only once the
+// handshake (including the signature check) has completed.
+static GEMINI_PENDING_PIN: SpinLock<Option<[u8; 32]>> = SpinLock::new(None);
+static GEMINI_PIN_MISMATCH: AtomicBool = AtomicBool::new(false);
+
+fn pinned_fingerprint(host: &str) -> Option<[u8; 32]> {
+    GEMINI_PINS
+        .lock()
+        .iter()
+        .find(|p| p.host.eq_ignore_ascii_case(host))
+        .map(|p| p.fingerprint)
+}
+
+fn commit_pending_pin(host: &str) {
+    let Some(fingerprint) = GEMINI_PENDING_PIN.lock().take() else {
+        return;
+    };
+    {
+        let mut pins = GEMINI_PINS.lock();
+        if pins.iter().any(|p| p.host.eq_ignore_ascii_case(host)) {
+            return;
+        }
+        if pins.len() >= GEMINI_MAX_PINS {
+            pins.remove(0);
+        }
+        pins.push(PinnedHost {
+            host: redux_netparse::ascii_lowercase(host),
+            fingerprint,
+        });
+    }
+    crate::println(alloc::format!("Gemini: certificado fijado para {} (TOFU).", host).as_str());
+}
+
+#[derive(Debug)]
+struct TofuVerifier {
+    host: String,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for TofuVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, Error> {
+        let fingerprint: [u8; 32] = Sha256::digest(end_entity.as_ref()).into();
+        match pinned_fingerprint(self.host.as_str()) {
+            Some(pinned) if pinned != fingerprint => {
+                GEMINI_PIN_MISMATCH.store(true, Ordering::Relaxed);
+                Err(Error::InvalidCertificate(CertificateError::ApplicationVerificationFailure))
+            }
+            Some(_) => Ok(ServerCertVerified::assertion()),
+            None => {
+                *GEMINI_PENDING_PIN.lock() = Some(fingerprint);
+                Ok(ServerCertVerified::assertion())
+            }
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+pub struct GeminiResponse {
+    /// URL that produced this response, after redirects.
+    pub url: String,
+    pub status: u8,
+    pub meta: String,
+    pub body: Vec<u8>,
+    pub redirects: usize,
+}
+
+fn fetch_once(url: &str, pump_ui: &mut impl FnMut()) -> Result<GeminiResponse, String> {
+    let target = parse_gemini_url(url).ok_or_else(|| String::from("URL gemini invalida"))?;
+    let verifier = Arc::new(TofuVerifier {
+        host: target.host.clone(),
+        algorithms: rustls_rustcrypto::provider().signature_verification_algorithms,
+    });
+    let tls = super::tls::TlsConnection::new_with_verifier(target.host.as_str(), verifier)
+        .ok_or_else(|| String::from("no se pudo iniciar TLS"))?;
+
+    *GEMINI_PENDING_PIN.lock() = None;
+    GEMINI_PIN_MISMATCH.store(false, Ordering::Relaxed);
+    let request = alloc::format!("{}\r\n", url);
+    let raw = super::tcp_request_until_close(
+        target.host.as_str(),
+        target.port,
+        Some(tls),
+        request.as_bytes(),
+        pump_ui,
+        GEMINI_TIMEOUT_TICKS,
+        GEMINI_MAX_RESPONSE_BYTES,
+    );
+    if GEMINI_PIN_MISMATCH.load(Ordering::Relaxed) {
+        return Err(alloc::format!(
+            "el certificado de {} no coincide con el fijado (TOFU). Si el cambio es legitimo: gemini forget {}",
+            target.host, target.host
+        ));
+    }
+    let raw = raw.ok_or_else(|| alloc::format!("sin respuesta de {}:{}", target.host, target.port))?;
+    commit_pending_pin(target.host.as_str());
+
+    let header = parse_gemini_header(raw.as_slice()).ok_or_else(|| String::from("cabecera de respuesta invalida"))?;
+    Ok(GeminiResponse {
+        url: String::from(url),
+        status: header.status,
+        meta: header.meta,
+        body: raw.get(header.body_offset..).unwrap_or(&[]).to_vec(),
+        redirects: 0,
+    })
+}
+
+/// Fetch `url`, following redirects that stay on `gemini://`.
+pub fn fetch(url: &str, pump_ui: &mut impl FnMut()) -> Result<GeminiResponse, String> {
+    let mut current = String::from(url.trim());
+    let mut redirects = 0usize;
+    loop {
+        let mut response = fetch_once(current.as_str(), pump_ui)?;
+        response.redirects = redirects;
+        if response.status / 10 != 3 || redirects >= GEMINI_MAX_REDIRECTS {
+            return Ok(response);
+        }
+        let next = resolve_reference(current.as_str(), response.meta.as_str());
+        if !starts_with_ignore_ascii_case(next.as_str(), "gemini://") {
+            // Cross-protocol redirects are shown as a link, not followed.
+            return Ok(response);
+        }
+        current = next;
+        redirects += 1;
+    }
+}
+
+pub fn forget_host(host: &str) -> bool {
+    let mut pins = GEMINI_PINS.lock();
+    let before = pins.len();
+    pins.retain(|p| !p.host.eq_ignore_ascii_case(host));
+    pins.len() != before
+}
+
+/// Shared implementation of `gemini [pins|forget <host>]`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut parts = args.split_whitespace();
+    let mut out = Vec::new();
+    match (parts.next().unwrap_or("pins"), parts.next()) {
+        ("pins", _) => {
+            let pins = GEMINI_PINS.lock();
+            out.push(alloc::format!("Gemini: {} certificado(s) fijado(s) (TOFU).", pins.len()));
+            for pin in pins.iter() {
+                let mut hex = String::new();
+                for b in pin.fingerprint.iter().take(8) {
+                    hex.push_str(alloc::format!("{:02x}", b).as_str());
+                }
+                out.push(alloc::format!("  {}  sha256:{}...", pin.host, hex));
+            }
+        }
+        ("forget", Some(host)) => {
+            if forget_host(host) {
+                out.push(alloc::format!("Gemini: certificado de {} olvidado.", host));
+            } else {
+                out.push(alloc::format!("Gemini: {} no tiene certificado fijado.", host));
+            }
+        }
+        _ => out.push(String::from("Uso: gemini [pins|forget <host>]")),
+    }
+    out
+}
@@ -0,0 +1,49 @@
+//! Gopher client (`gopher://`, RFC 1436). Plain TCP, one selector per
+//! connection; the server closes the socket when the item is complete.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use redux_netparse::gopher::{gopher_request_line, parse_gopher_url};
+
+const GOPHER_MAX_RESPONSE_BYTES: usize = 2 * 1024 * 1024;
+const GOPHER_TIMEOUT_TICKS: u64 = 3_000;
+
+pub struct GopherResponse {
+    pub url: String,
+    /// Item type from the URL (`1` menu, `0` text, `7` search, ...).
+    pub item_type: u8,
+    /// Raw item bytes. Empty for a type `7` URL without a query: the caller
+    /// has to ask for the search string first.
+    pub body: Vec<u8>,
+}
+
+pub fn fetch(url: &str, pump_ui: &mut impl FnMut()) -> Result<GopherResponse, String> {
+    let url = url.trim();
+    let target = parse_gopher_url(url).ok_or_else(|| String::from("URL gopher invalida"))?;
+    if target.item_type == b'7' && target.query.is_none() {
+        return Ok(GopherResponse {
+            url: String::from(url),
+            item_type: target.item_type,
+            body: Vec::new(),
+        });
+    }
+
+    let request = gopher_request_line(&target);
+    let body = super::tcp_request_until_close(
+        target.host.as_str(),
+        target.port,
+        None,
+        request.as_bytes(),
+        pump_ui,
+        GOPHER_TIMEOUT_TICKS,
+        GOPHER_MAX_RESPONSE_BYTES,
+    )
+    .ok_or_else(|| alloc::format!("sin respuesta de {}:{}", target.host, target.port))?;
+
+    Ok(GopherResponse {
+        url: String::from(url),
+        item_type: target.item_type,
+        body,
+    })
+}
@@ -0,0 +1,230 @@
+//! IPP client (RFC 8010 / 8011) for IPP Everywhere printers.
+//!
+//! Every operation is one `application/ipp` POST over `HttpRequest` to the
+//! HTTP URL of the printer URI (`ipp://host/ipp/print` goes to
+//! `http://host:631/ipp/print`). Only the operations a print client needs
+//! are here: Get-Printer-Attributes, Print-Job, Get-Job-Attributes and
+//! Cancel-Job.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use redux_netparse::http::parse_http_headers;
+use redux_netparse::ipp::{
+    ipp_http_url, ipp_status_text, parse_ipp_message, IppMessage, IppRequest, IppValue, IPP_OP_CANCEL_JOB,
+    IPP_OP_GET_JOB_ATTRIBUTES, IPP_OP_GET_PRINTER_ATTRIBUTES, IPP_OP_PRINT_JOB, IPP_RESOLUTION_DPI, IPP_TAG_JOB,
+    IPP_TAG_KEYWORD, IPP_TAG_MIME_TYPE, IPP_TAG_NAME, IPP_TAG_PRINTER, IPP_TAG_URI,
+};
+
+use super::request::{HttpMethod, HttpRequest};
+
+const IPP_TIMEOUT_MS: u64 = 8_000;
+/// Print-Job uploads the whole document, so it gets longer.
+const IPP_PRINT_TIMEOUT_MS: u64 = 60_000;
+const IPP_LANGUAGE: &str = "es";
+const PRINTER_ATTRIBUTES: [&str; 9] = [
+    "printer-name",
+    "printer-make-and-model",
+    "printer-state",
+    "printer-state-reasons",
+    "document-format-supported",
+    "pwg-raster-document-resolution-supported",
+    "pwg-raster-document-type-supported",
+    "color-supported",
+    "media-default",
+];
+
+static REQUEST_ID: AtomicU32 = AtomicU32::new(1);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PrinterInfo {
+    pub name: String,
+    pub make_model: String,
+    /// `printer-state`: 3 idle, 4 processing, 5 stopped.
+    pub state: i32,
+    pub state_reasons: Vec<String>,
+    pub formats: Vec<String>,
+    /// PWG raster resolutions in dpi (square ones only).
+    pub resolutions: Vec<u32>,
+    /// `pwg-raster-document-type-supported`, e.g. "sgray_8", "srgb_8".
+    pub raster_types: Vec<String>,
+    pub color: bool,
+    pub media_default: String,
+}
+
+impl PrinterInfo {
+    pub fn supports_format(&self, format: &str) -> bool {
+        self.formats.iter().any(|f| f.eq_ignore_ascii_case(format))
+    }
+
+    pub fn supports_raster_type(&self, raster_type: &str) -> bool {
+        self.raster_types.iter().any(|t| t.eq_ignore_ascii_case(raster_type))
+    }
+}
+
+pub fn printer_info(message: &IppMessage) -> PrinterInfo {
+    let owned = |name: &str| -> Vec<String> {
+        message
+            .strings(IPP_TAG_PRINTER, name)
+            .into_iter()
+            .map(String::from)
+            .collect()
+    };
+    let resolutions = message
+        .attribute(IPP_TAG_PRINTER, "pwg-raster-document-resolution-supported")
+        .map(|a| {
+            a.values
+                .iter()
+                .filter_map(|v| match v {
+                    IppValue::Resolution { x, y, units } if x == y && *units == IPP_RESOLUTION_DPI && *x > 0 => {
+                        Some(*x as u32)
+                    }
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let color = matches!(
+        message
+            .attribute(IPP_TAG_PRINTER, "color-supported")
+            .and_then(|a| a.values.first()),
+        Some(IppValue::Boolean(true))
+    );
+    PrinterInfo {
+        name: String::from(message.first_str(IPP_TAG_PRINTER, "printer-name").unwrap_or("")),
+        make_model: String::from(
+            message
+                .first_str(IPP_TAG_PRINTER, "printer-make-and-model")
+                .unwrap_or(""),
+        ),
+        state: message.first_int(IPP_TAG_PRINTER, "printer-state").unwrap_or(0),
+        state_reasons: owned("printer-state-reasons"),
+        formats: owned("document-format-supported"),
+        resolutions,
+        raster_types: owned("pwg-raster-document-type-supported"),
+        color,
+        media_default: String::from(message.first_str(IPP_TAG_PRINTER, "media-default").unwrap_or("")),
+    }
+}
+
+pub fn printer_state_text(state: i32) -> &'static str {
+    match state {
+        3 => "lista",
+        4 => "imprimiendo",
+        5 => "detenida",
+        _ => "desconocido",
+    }
+}
+
+fn next_request_id() -> u32 {
+    REQUEST_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// POST `body` to the printer and parse the IPP response. Errors are short
+/// Spanish messages for the shell and the print dialog.
+fn post(uri: &str, body: Vec<u8>, timeout_ms: u64, pump_ui: &mut impl FnMut()) -> Result<IppMessage, String> {
+    let url = ipp_http_url(uri).ok_or_else(|| String::from("URI de impresora invalida"))?;
+    let raw = HttpRequest::new(HttpMethod::Post, url.as_str())
+        .body("application/ipp", body)
+        .timeout_ms(timeout_ms)
+        .send(pump_ui)
+        .ok_or_else(|| String::from("la impresora no responde"))?;
+    let head = parse_http_headers(raw.as_slice());
+    match head.status_code {
+        Some(200) => {}
+        Some(code) => return Err(alloc::format!("HTTP {}", code)),
+        None => return Err(String::from("respuesta HTTP invalida")),
+    }
+    let message = raw
+        .get(head.body_offset..)
+        .and_then(parse_ipp_message)
+        .ok_or_else(|| String::from("respuesta IPP invalida"))?;
+    if !message.is_successful() {
+        return Err(alloc::format!("IPP {}", ipp_status_text(message.code)));
+    }
+    Ok(message)
+}
+
+pub fn get_printer_attributes(uri: &str, pump_ui: &mut impl FnMut()) -> Result<PrinterInfo, String> {
+    let mut request = IppRequest::new(IPP_OP_GET_PRINTER_ATTRIBUTES, next_request_id(), IPP_LANGUAGE);
+    request.string(IPP_TAG_URI, "printer-uri", uri).strings(
+        IPP_TAG_KEYWORD,
+        "requested-attributes",
+        &PRINTER_ATTRIBUTES,
+    );
+    let message = post(uri, request.finish(&[]), IPP_TIMEOUT_MS, pump_ui)?;
+    Ok(printer_info(&message))
+}
+
+/// Submit `data` as one job. Returns the printer's job id and job state.
+pub fn print_job(
+    uri: &str,
+    job_name: &str,
+    user: &str,
+    format: &str,
+    copies: u32,
+    data: &[u8],
+    pump_ui: &mut impl FnMut(),
+) -> Result<(i32, i32), String> {
+    let mut request = IppRequest::new(IPP_OP_PRINT_JOB, next_request_id(), IPP_LANGUAGE);
+    request
+        .string(IPP_TAG_URI, "printer-uri", uri)
+        .string(IPP_TAG_NAME, "requesting-user-name", user)
+        .string(IPP_TAG_NAME, "job-name", job_name)
+        .string(IPP_TAG_MIME_TYPE, "document-format", format);
+    if copies > 1 {
+        request.group(IPP_TAG_JOB).integer("copies", copies as i32);
+    }
+    let message = post(uri, request.finish(data), IPP_PRINT_TIMEOUT_MS, pump_ui)?;
+    let job_id = message
+        .first_int(IPP_TAG_JOB, "job-id")
+        .ok_or_else(|| String::from("la impresora no devolvio job-id"))?;
+    Ok((job_id, message.first_int(IPP_TAG_JOB, "job-state").unwrap_or(0)))
+}
+
+pub fn get_job_state(uri: &str, job_id: i32, pump_ui: &mut impl FnMut()) -> Result<i32, String> {
+    let mut request = IppRequest::new(IPP_OP_GET_JOB_ATTRIBUTES, next_request_id(), IPP_LANGUAGE);
+    request
+        .string(IPP_TAG_URI, "printer-uri", uri)
+        .integer("job-id", job_id)
+        .strings(IPP_TAG_KEYWORD, "requested-attributes", &["job-state"]);
+    let message = post(uri, request.finish(&[]), IPP_TIMEOUT_MS, pump_ui)?;
+    message
+        .first_int(IPP_TAG_JOB, "job-state")
+        .ok_or_else(|| String::from("la impresora no devolvio job-state"))
+}
+
+pub fn cancel_job(uri: &str, job_id: i32, user: &str, pump_ui: &mut impl FnMut()) -> Result<(), String> {
+    let mut request = IppRequest::new(IPP_OP_CANCEL_JOB, next_request_id(), IPP_LANGUAGE);
+    request
+        .string(IPP_TAG_URI, "printer-uri", uri)
+        .integer("job-id", job_id)
+        .string(IPP_TAG_NAME, "requesting-user-name", user);
+    post(uri, request.finish(&[]), IPP_TIMEOUT_MS, pump_ui).map(|_| ())
+}
+
+crate::selftest::kernel_tests! {
+    "net_ipp";
+
+    fn printer_attributes_become_info() {
+        let mut response = IppRequest::new(0, 3, "en");
+        response
+            .group(IPP_TAG_PRINTER)
+            .string(IPP_TAG_NAME, "printer-name", "Oficina")
+            .enumeration("printer-state", 3)
+            .strings(IPP_TAG_MIME_TYPE, "document-format-supported", &["application/pdf", "image/pwg-raster"])
+            .strings(IPP_TAG_KEYWORD, "pwg-raster-document-type-supported", &["sgray_8", "srgb_8"])
+            .boolean("color-supported", true)
+            .string(IPP_TAG_KEYWORD, "media-default", "na_letter_8.5x11in");
+        let bytes = response.finish(&[]);
+        let message = parse_ipp_message(&bytes).ok_or("respuesta")?;
+        let info = printer_info(&message);
+        crate::selftest::ensure_eq(info.name.as_str(), "Oficina", "nombre")?;
+        crate::selftest::ensure_eq(info.state, 3, "estado")?;
+        crate::selftest::ensure(info.supports_format("APPLICATION/PDF"), "pdf")?;
+        crate::selftest::ensure(info.supports_raster_type("srgb_8") && info.color, "color")?;
+        crate::selftest::ensure_eq(info.media_default.as_str(), "na_letter_8.5x11in", "papel")?;
+        crate::selftest::ensure(info.resolutions.is_empty(), "sin resoluciones")
+    }
+}
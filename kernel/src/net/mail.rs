@@ -0,0 +1,758 @@
+//! Mail client behind the `mail` command and the Mail window (Herramientas >
+//! Correo): IMAP4rev1 for the INBOX, SMTP submission for sending.
+//!
+//! ReduxOS has a single local user, so there is one account and it lives on
+//! the active volume as `MAIL/ACCOUNT.CFG` (`clave=valor` lines, like
+//! ZENOXOS.INI). The password is stored in clear text next to it; the file is
+//! only as private as the disk.
+//!
+//! Every operation opens its own connection, logs in, does its work and logs
+//! out, so nothing is left half-open while the machine sits idle. IMAP and
+//! SMTP default to implicit TLS (993) and STARTTLS (587); plain connections
+//! are only used when the account says so.
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use smoltcp::iface::{Interface, SocketHandle, SocketSet};
+use smoltcp::socket::tcp;
+
+use redux_netparse::imap::{
+    imap_quote, parse_exists, parse_fetch, parse_imap_greeting, parse_imap_response, ImapResponse, ImapStatus,
+    IMAPS_DEFAULT_PORT, IMAP_DEFAULT_PORT,
+};
+use redux_netparse::mime::{
+    base64_encode, compose_message, extract_address, format_date, parse_message, MailMessage,
+};
+use redux_netparse::smtp::{
+    auth_plain, dot_stuff, ehlo_auth_mechanisms, ehlo_has, parse_smtp_reply, SmtpReply, SMTPS_PORT,
+    SMTP_SUBMISSION_PORT,
+};
+
+use super::tls::{self, TlsConnection};
+use super::{IFACE, NET_BLOCKING_LOOP_STALL_US, SOCKETS, TLS_MAX_RECORD_PLAINTEXT};
+use crate::spinlock::SpinLock;
+
+const MAIL_TIMEOUT_TICKS: u64 = 3_000;
+const MAIL_MAX_RESPONSE_BYTES: usize = 8 * 1024 * 1024;
+const MAIL_DIR: &str = "MAIL";
+const MAIL_ACCOUNT_FILE: &str = "ACCOUNT.CFG";
+const MAIL_EHLO_NAME: &str = "reduxos.local";
+pub const MAIL_INBOX_DEFAULT_COUNT: u32 = 30;
+const MAIL_INBOX_MAX_COUNT: u32 = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MailSecurity {
+    /// TLS from the first byte (IMAPS 993, SMTPS 465).
+    Tls,
+    /// Plain connection upgraded with `STARTTLS` before logging in.
+    StartTls,
+    Plain,
+}
+
+impl MailSecurity {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "tls" | "ssl" => Some(Self::Tls),
+            "starttls" => Some(Self::StartTls),
+            "plain" | "none" | "ninguna" => Some(Self::Plain),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Tls => "tls",
+            Self::StartTls => "starttls",
+            Self::Plain => "plain",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MailAccount {
+    pub email: String,
+    pub name: String,
+    pub user: String,
+    pub password: String,
+    pub imap_host: String,
+    pub imap_port: u16,
+    pub imap_security: MailSecurity,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_security: MailSecurity,
+}
+
+/// Keys accepted by `mail set` and the account file, in file order.
+pub const MAIL_ACCOUNT_KEYS: [&str; 10] = [
+    "email",
+    "name",
+    "user",
+    "password",
+    "imap_host",
+    "imap_security",
+    "imap_port",
+    "smtp_host",
+    "smtp_security",
+    "smtp_port",
+];
+
+impl MailAccount {
+    /// Account for `email` with the usual `imap.<dominio>` /
+    /// `smtp.<dominio>` servers, IMAPS and submission with STARTTLS.
+    pub fn for_address(email: &str, password: &str) -> Option<Self> {
+        let email = extract_address(email)?;
+        let domain = email.rsplit('@').next().unwrap_or("").to_ascii_lowercase();
+        Some(Self {
+            user: email.clone(),
+            email,
+            name: String::new(),
+            password: String::from(password),
+            imap_host: alloc::format!("imap.{}", domain),
+            imap_port: IMAPS_DEFAULT_PORT,
+            imap_security: MailSecurity::Tls,
+            smtp_host: alloc::format!("smtp.{}", domain),
+            smtp_port: SMTP_SUBMISSION_PORT,
+            smtp_security: MailSecurity::StartTls,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        Some(match key {
+            "email" => self.email.clone(),
+            "name" => self.name.clone(),
+            "user" => self.user.clone(),
+            "password" => self.password.clone(),
+            "imap_host" => self.imap_host.clone(),
+            "imap_port" => alloc::format!("{}", self.imap_port),
+            "imap_security" => String::from(self.imap_security.as_str()),
+            "smtp_host" => self.smtp_host.clone(),
+            "smtp_port" => alloc::format!("{}", self.smtp_port),
+            "smtp_security" => String::from(self.smtp_security.as_str()),
+            _ => return None,
+        })
+    }
+
+    /// Change one setting. Switching the security of a server also moves it
+    /// to that mode's standard port.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        if value.bytes().any(|b| b == b'\r' || b == b'\n') {
+            return Err(String::from("valor invalido"));
+        }
+        let port = || value.parse::<u16>().ok().filter(|&p| p != 0).ok_or_else(|| String::from("puerto invalido"));
+        let security = || {
+            MailSecurity::parse(value).ok_or_else(|| String::from("seguridad invalida (tls|starttls|plain)"))
+        };
+        match key {
+            "email" => {
+                self.email = extract_address(value).ok_or_else(|| String::from("direccion invalida"))?;
+            }
+            "name" => self.name = String::from(value),
+            "user" => self.user = String::from(value),
+            "password" => self.password = String::from(value),
+            "imap_host" => self.imap_host = String::from(value),
+            "imap_port" => self.imap_port = port()?,
+            "imap_security" => {
+                self.imap_security = security()?;
+                self.imap_port = match self.imap_security {
+                    MailSecurity::Tls => IMAPS_DEFAULT_PORT,
+                    _ => IMAP_DEFAULT_PORT,
+                };
+            }
+            "smtp_host" => self.smtp_host = String::from(value),
+            "smtp_port" => self.smtp_port = port()?,
+            "smtp_security" => {
+                self.smtp_security = security()?;
+                self.smtp_port = match self.smtp_security {
+                    MailSecurity::Tls => SMTPS_PORT,
+                    _ => SMTP_SUBMISSION_PORT,
+                };
+            }
+            _ => return Err(alloc::format!("clave desconocida: {}", key)),
+        }
+        Ok(())
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let mut account = Self::for_address("usuario@localhost", "")?;
+        account.email.clear();
+        account.user.clear();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                // Ports come after security in the file, so explicit ports win.
+                let _ = account.set(key.trim(), value);
+            }
+        }
+        if account.email.is_empty() {
+            return None;
+        }
+        if account.user.is_empty() {
+            account.user = account.email.clone();
+        }
+        Some(account)
+    }
+
+    fn serialize(&self) -> String {
+        let mut out = String::from("# Cuenta de correo de ReduxOS\r\n");
+        for key in MAIL_ACCOUNT_KEYS.iter() {
+            out.push_str(alloc::format!("{}={}\r\n", key, self.get(key).unwrap_or_default()).as_str());
+        }
+        out
+    }
+
+    fn from_header(&self) -> String {
+        if self.name.is_empty() {
+            self.email.clone()
+        } else {
+            alloc::format!("{} <{}>", redux_netparse::mime::encode_header_word(self.name.as_str()), self.email)
+        }
+    }
+}
+
+pub fn load_account() -> Option<MailAccount> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let (_, dir) = fat.resolve_path(fat.root_cluster, alloc::format!("{}/", MAIL_DIR).as_str()).ok()?;
+    let raw = fat.read_file_in_dir(dir, MAIL_ACCOUNT_FILE).ok()?;
+    MailAccount::parse(String::from_utf8_lossy(raw.as_slice()).as_ref())
+}
+
+pub fn save_account(account: &MailAccount) -> Result<(), String> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let dir = fat.ensure_subdirectory(fat.root_cluster, MAIL_DIR).map_err(String::from)?;
+    fat.write_text_file_in_dir(dir, MAIL_ACCOUNT_FILE, account.serialize().as_bytes())
+        .map_err(String::from)
+}
+
+fn account() -> Result<MailAccount, String> {
+    load_account().ok_or_else(|| String::from("sin cuenta de correo (usa: mail setup <email> <clave>)"))
+}
+
+/// One INBOX entry as shown in lists.
+#[derive(Clone, Debug, Default)]
+pub struct MailSummary {
+    pub uid: u32,
+    pub from: String,
+    pub subject: String,
+    pub date: String,
+    pub size: u32,
+    pub seen: bool,
+}
+
+/// Last fetched INBOX, newest first.
+static MAIL_INBOX: SpinLock<Vec<MailSummary>> = SpinLock::new(Vec::new());
+
+/// Snapshot of the last fetched INBOX.
+pub fn cached_inbox() -> Vec<MailSummary> {
+    MAIL_INBOX.lock().clone()
+}
+
+/// The smoltcp interface and socket set.
+///
+/// # Safety
+///
+/// Kernel thread only, and nothing else may use `IFACE` or `SOCKETS` while
+/// the borrows are alive: the `pump_ui` callbacks passed down here redraw
+/// the desktop but must never reach `net::poll`.
+unsafe fn stack() -> Result<(&'static mut Interface, &'static mut SocketSet<'static>), String> {
+    unsafe {
+        match (IFACE.get_mut().as_mut(), SOCKETS.get_mut().as_mut()) {
+            (Some(iface), Some(sockets)) => Ok((iface, sockets)),
+            _ => Err(String::from("la red no esta inicializada")),
+        }
+    }
+}
+
+struct MailConn {
+    host: String,
+    handle: SocketHandle,
+    tls: Option<TlsConnection>,
+    rx: Vec<u8>,
+    next_tag: u32,
+}
+
+impl MailConn {
+    fn open(host: &str, port: u16, security: MailSecurity, pump_ui: &mut impl FnMut()) -> Result<Self, String> {
+        // SAFETY: kernel thread; this borrow is dead before anything else takes the stack.
+        let (iface, sockets) = unsafe { stack() }?;
+        let addr = super::resolve_ipv4_blocking(iface, sockets, host, pump_ui, MAIL_TIMEOUT_TICKS)
+            .ok_or_else(|| alloc::format!("no se pudo resolver {}", host))?;
+        let handle = super::tcp_connect_blocking(iface, sockets, addr, port, pump_ui, MAIL_TIMEOUT_TICKS)
+            .ok_or_else(|| alloc::format!("no se pudo conectar a {}:{}", host, port))?;
+        let mut conn = Self {
+            host: String::from(host),
+            handle,
+            tls: None,
+            rx: Vec::new(),
+            next_tag: 1,
+        };
+        if security == MailSecurity::Tls {
+            if let Err(err) = conn.start_tls(pump_ui) {
+                conn.close(pump_ui);
+                return Err(err);
+            }
+        }
+        Ok(conn)
+    }
+
+    fn start_tls(&mut self, pump_ui: &mut impl FnMut()) -> Result<(), String> {
+        let config = Arc::new(tls::webpki_client_config());
+        let mut tls = TlsConnection::new_shared(self.host.as_str(), &config)
+            .ok_or_else(|| String::from("no se pudo iniciar TLS"))?;
+        // SAFETY: kernel thread; this borrow is dead before anything else takes the stack.
+        let (iface, sockets) = unsafe { stack() }?;
+        if !super::tls_handshake_blocking(iface, sockets, self.handle, &mut tls, pump_ui, MAIL_TIMEOUT_TICKS) {
+            return Err(alloc::format!("fallo el handshake TLS con {}", self.host));
+        }
+        // Anything buffered before the handshake came in the clear.
+        self.rx.clear();
+        self.tls = Some(tls);
+        Ok(())
+    }
+
+    fn send(&mut self, data: &[u8], pump_ui: &mut impl FnMut()) -> Result<(), String> {
+        // SAFETY: kernel thread; this borrow is dead before anything else takes the stack.
+        let (iface, sockets) = unsafe { stack() }?;
+        super::tcp_send_all_blocking(iface, sockets, self.handle, self.tls.as_mut(), data, pump_ui, MAIL_TIMEOUT_TICKS)
+            .map_err(String::from)
+    }
+
+    /// Read until `parse` recognises a complete response at the start of
+    /// the buffer; `parse` returns it with the number of bytes it used.
+    fn read_until<T>(
+        &mut self,
+        pump_ui: &mut impl FnMut(),
+        mut parse: impl FnMut(&[u8]) -> Option<(T, usize)>,
+    ) -> Result<T, String> {
+        // SAFETY: kernel thread; this borrow is dead before anything else takes the stack.
+        let (iface, sockets) = unsafe { stack() }?;
+        let mut buf = alloc::vec![0u8; TLS_MAX_RECORD_PLAINTEXT];
+        let mut last_data = crate::timer::ticks();
+        loop {
+            if let Some((value, used)) = parse(self.rx.as_slice()) {
+                self.rx.drain(..used);
+                return Ok(value);
+            }
+            if self.rx.len() > MAIL_MAX_RESPONSE_BYTES {
+                return Err(String::from("respuesta del servidor demasiado larga"));
+            }
+
+            pump_ui();
+            super::net_poll_blocking(iface, sockets);
+            let socket = sockets.get_mut::<tcp::Socket>(self.handle);
+            let len = match self.tls.as_mut() {
+                Some(tls) => tls.read(socket, &mut buf),
+                None => socket.recv_slice(&mut buf).unwrap_or(0),
+            };
+            if len > 0 {
+                self.rx.extend_from_slice(&buf[..len]);
+                last_data = crate::timer::ticks();
+                continue;
+            }
+            if !socket.may_recv() {
+                return Err(String::from("conexion cerrada por el servidor"));
+            }
+            if crate::timer::ticks() - last_data > MAIL_TIMEOUT_TICKS {
+                return Err(String::from("timeout esperando respuesta"));
+            }
+            uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
+        }
+    }
+
+    fn close(self, pump_ui: &mut impl FnMut()) {
+        // SAFETY: kernel thread; the borrow ends with the block.
+        if let Ok((iface, sockets)) = unsafe { stack() } {
+            super::tcp_close_blocking(iface, sockets, self.handle, pump_ui, MAIL_TIMEOUT_TICKS);
+        }
+    }
+
+    // IMAP
+
+    /// Send a tagged command and wait for its completion. `NO`/`BAD` become
+    /// errors carrying the server's text.
+    fn imap(&mut self, command: &str, pump_ui: &mut impl FnMut()) -> Result<ImapResponse, String> {
+        let tag = alloc::format!("A{}", self.next_tag);
+        self.next_tag += 1;
+        self.send(alloc::format!("{} {}\r\n", tag, command).as_bytes(), pump_ui)?;
+        let response = self.read_until(pump_ui, |rx| {
+            parse_imap_response(rx, tag.as_str()).map(|r| {
+                let used = r.consumed;
+                (r, used)
+            })
+        })?;
+        if response.status != ImapStatus::Ok {
+            let verb = command.split(' ').next().unwrap_or("");
+            return Err(alloc::format!("{}: {}", verb, response.text));
+        }
+        Ok(response)
+    }
+
+    fn imap_login(account: &MailAccount, pump_ui: &mut impl FnMut()) -> Result<Self, String> {
+        let mut conn = Self::open(account.imap_host.as_str(), account.imap_port, account.imap_security, pump_ui)?;
+        match conn.imap_login_inner(account, pump_ui) {
+            Ok(()) => Ok(conn),
+            Err(err) => {
+                conn.close(pump_ui);
+                Err(err)
+            }
+        }
+    }
+
+    fn imap_login_inner(&mut self, account: &MailAccount, pump_ui: &mut impl FnMut()) -> Result<(), String> {
+        if !self.read_until(pump_ui, parse_imap_greeting)? {
+            return Err(alloc::format!("{} rechazo la conexion", self.host));
+        }
+        if account.imap_security == MailSecurity::StartTls {
+            self.imap("STARTTLS", pump_ui)?;
+            self.start_tls(pump_ui)?;
+        }
+        let user = imap_quote(account.user.as_str()).ok_or_else(|| String::from("usuario invalido"))?;
+        let password = imap_quote(account.password.as_str()).ok_or_else(|| String::from("clave invalida"))?;
+        self.imap(alloc::format!("LOGIN {} {}", user, password).as_str(), pump_ui)
+            .map_err(|e| alloc::format!("login rechazado: {}", e))?;
+        Ok(())
+    }
+
+    fn imap_logout(mut self, pump_ui: &mut impl FnMut()) {
+        let _ = self.imap("LOGOUT", pump_ui);
+        self.close(pump_ui);
+    }
+
+    // SMTP
+
+    fn smtp_reply(&mut self, pump_ui: &mut impl FnMut()) -> Result<SmtpReply, String> {
+        self.read_until(pump_ui, |rx| {
+            parse_smtp_reply(rx).map(|r| {
+                let used = r.consumed;
+                (r, used)
+            })
+        })
+    }
+
+    /// Send one SMTP line and require a reply of `class` (2 or 3).
+    fn smtp(&mut self, line: &str, class: u16, pump_ui: &mut impl FnMut()) -> Result<SmtpReply, String> {
+        self.send(alloc::format!("{}\r\n", line).as_bytes(), pump_ui)?;
+        let reply = self.smtp_reply(pump_ui)?;
+        if reply.class() != class {
+            let verb = line.split([' ', ':']).next().unwrap_or("");
+            return Err(alloc::format!("{}: {} {}", verb, reply.code, reply.message()));
+        }
+        Ok(reply)
+    }
+}
+
+/// Fetch the headers of the newest `count` INBOX messages into the cache.
+/// Returns the total number of messages in the INBOX.
+pub fn refresh_inbox(count: u32, pump_ui: &mut impl FnMut()) -> Result<u32, String> {
+    let account = account()?;
+    let mut conn = MailConn::imap_login(&account, pump_ui)?;
+    let result = fetch_summaries(&mut conn, count.clamp(1, MAIL_INBOX_MAX_COUNT), pump_ui);
+    conn.imap_logout(pump_ui);
+    let (exists, mut list) = result?;
+    list.sort_by(|a, b| b.1.cmp(&a.1));
+    *MAIL_INBOX.lock() = list.into_iter().map(|(summary, _)| summary).collect();
+    Ok(exists)
+}
+
+fn fetch_summaries(
+    conn: &mut MailConn,
+    count: u32,
+    pump_ui: &mut impl FnMut(),
+) -> Result<(u32, Vec<(MailSummary, u32)>), String> {
+    let selected = conn.imap("SELECT INBOX", pump_ui)?;
+    let exists = selected.untagged.iter().filter_map(parse_exists).last().unwrap_or(0);
+    let mut out = Vec::new();
+    if exists == 0 {
+        return Ok((0, out));
+    }
+    let first = exists.saturating_sub(count - 1).max(1);
+    let response = conn.imap(
+        alloc::format!(
+            "FETCH {}:{} (UID RFC822.SIZE FLAGS BODY.PEEK[HEADER.FIELDS (FROM SUBJECT DATE)])",
+            first, exists
+        )
+        .as_str(),
+        pump_ui,
+    )?;
+    for line in response.untagged.iter() {
+        let Some(fetch) = parse_fetch(line) else {
+            continue;
+        };
+        let Some(uid) = fetch.uid else {
+            continue;
+        };
+        let headers = parse_message(fetch.body.as_deref().unwrap_or(&[]));
+        out.push((
+            MailSummary {
+                uid,
+                from: headers.from,
+                subject: headers.subject,
+                date: headers.date,
+                size: fetch.size.unwrap_or(0),
+                seen: fetch.seen,
+            },
+            fetch.seq,
+        ));
+    }
+    Ok((exists, out))
+}
+
+/// Download and decode one message by UID. Marks it as read on the server.
+pub fn fetch_message(uid: u32, pump_ui: &mut impl FnMut()) -> Result<MailMessage, String> {
+    let account = account()?;
+    let mut conn = MailConn::imap_login(&account, pump_ui)?;
+    let result = conn
+        .imap("SELECT INBOX", pump_ui)
+        .and_then(|_| conn.imap(alloc::format!("UID FETCH {} (BODY[])", uid).as_str(), pump_ui));
+    conn.imap_logout(pump_ui);
+    let response = result?;
+    let raw = response
+        .untagged
+        .iter()
+        .filter_map(parse_fetch)
+        .find(|f| f.uid == Some(uid))
+        .and_then(|f| f.body)
+        .ok_or_else(|| alloc::format!("mensaje {} no encontrado", uid))?;
+    if let Some(summary) = MAIL_INBOX.lock().iter_mut().find(|s| s.uid == uid) {
+        summary.seen = true;
+    }
+    Ok(parse_message(raw.as_slice()))
+}
+
+/// Recipients from a `To` field: comma or semicolon separated addresses.
+pub fn parse_recipients(to: &str) -> Result<Vec<String>, String> {
+    let mut out = Vec::new();
+    for part in to.split([',', ';']) {
+        if part.trim().is_empty() {
+            continue;
+        }
+        out.push(extract_address(part).ok_or_else(|| alloc::format!("direccion invalida: {}", part.trim()))?);
+    }
+    if out.is_empty() {
+        return Err(String::from("falta el destinatario"));
+    }
+    Ok(out)
+}
+
+/// Submit a plain-text message through the account's SMTP server.
+pub fn send_message(to: &str, subject: &str, body: &str, pump_ui: &mut impl FnMut()) -> Result<(), String> {
+    let account = account()?;
+    let recipients = parse_recipients(to)?;
+    let now = crate::timer::wall_clock_unix_millis() / 1000;
+    let date = format_date(now, crate::timer::wall_clock_timezone_offset_minutes());
+    let domain = account.email.rsplit('@').next().unwrap_or("localhost");
+    let message_id = alloc::format!("{}.{}@{}", now, crate::timer::ticks(), domain);
+    let to_header = recipients.join(", ");
+    let message = compose_message(
+        account.from_header().as_str(),
+        to_header.as_str(),
+        subject,
+        date.as_str(),
+        message_id.as_str(),
+        body,
+    );
+
+    let mut conn = MailConn::open(account.smtp_host.as_str(), account.smtp_port, account.smtp_security, pump_ui)?;
+    let result = submit(&mut conn, &account, &recipients, message.as_bytes(), pump_ui);
+    if result.is_ok() {
+        let _ = conn.smtp("QUIT", 2, pump_ui);
+    }
+    conn.close(pump_ui);
+    result
+}
+
+fn submit(
+    conn: &mut MailConn,
+    account: &MailAccount,
+    recipients: &[String],
+    message: &[u8],
+    pump_ui: &mut impl FnMut(),
+) -> Result<(), String> {
+    let greeting = conn.smtp_reply(pump_ui)?;
+    if greeting.code != 220 {
+        return Err(alloc::format!("{} {}", greeting.code, greeting.message()));
+    }
+    let ehlo = alloc::format!("EHLO {}", MAIL_EHLO_NAME);
+    let mut caps = conn.smtp(ehlo.as_str(), 2, pump_ui)?;
+    if account.smtp_security == MailSecurity::StartTls {
+        if !ehlo_has(&caps, "STARTTLS") {
+            return Err(String::from("el servidor no admite STARTTLS"));
+        }
+        conn.smtp("STARTTLS", 2, pump_ui)?;
+        conn.start_tls(pump_ui)?;
+        // Capabilities sent before TLS must not be trusted (RFC 3207).
+        caps = conn.smtp(ehlo.as_str(), 2, pump_ui)?;
+    }
+
+    let mechanisms = ehlo_auth_mechanisms(&caps);
+    if mechanisms.iter().any(|m| m == "PLAIN") {
+        let line = alloc::format!("AUTH PLAIN {}", auth_plain(account.user.as_str(), account.password.as_str()));
+        conn.smtp(line.as_str(), 2, pump_ui).map_err(|_| String::from("login SMTP rechazado"))?;
+    } else if mechanisms.iter().any(|m| m == "LOGIN") {
+        conn.smtp("AUTH LOGIN", 3, pump_ui)?;
+        conn.smtp(base64_encode(account.user.as_bytes()).as_str(), 3, pump_ui)?;
+        conn.smtp(base64_encode(account.password.as_bytes()).as_str(), 2, pump_ui)
+            .map_err(|_| String::from("login SMTP rechazado"))?;
+    } else if !mechanisms.is_empty() {
+        return Err(alloc::format!("ningun metodo AUTH compatible ({})", mechanisms.join(" ")));
+    }
+
+    conn.smtp(alloc::format!("MAIL FROM:<{}>", account.email).as_str(), 2, pump_ui)?;
+    for rcpt in recipients.iter() {
+        conn.smtp(alloc::format!("RCPT TO:<{}>", rcpt).as_str(), 2, pump_ui)?;
+    }
+    conn.smtp("DATA", 3, pump_ui)?;
+    conn.send(dot_stuff(message).as_slice(), pump_ui)?;
+    let done = conn.smtp_reply(pump_ui)?;
+    if done.class() != 2 {
+        return Err(alloc::format!("DATA: {} {}", done.code, done.message()));
+    }
+    Ok(())
+}
+
+pub fn status_line() -> String {
+    match load_account() {
+        Some(a) => alloc::format!(
+            "Correo: {} - IMAP {}:{} ({}) - SMTP {}:{} ({})",
+            a.email,
+            a.imap_host,
+            a.imap_port,
+            a.imap_security.as_str(),
+            a.smtp_host,
+            a.smtp_port,
+            a.smtp_security.as_str()
+        ),
+        None => String::from("Correo: sin cuenta configurada"),
+    }
+}
+
+/// One line per cached INBOX entry, numbered from 1 (newest).
+pub fn inbox_lines() -> Vec<String> {
+    let inbox = cached_inbox();
+    let mut out = Vec::new();
+    for (i, m) in inbox.iter().enumerate() {
+        out.push(alloc::format!(
+            "{:>3} {} {:<24} {}",
+            i + 1,
+            if m.seen { ' ' } else { '*' },
+            m.from.chars().take(24).collect::<String>(),
+            m.subject
+        ));
+    }
+    if out.is_empty() {
+        out.push(String::from("  (bandeja vacia)"));
+    }
+    out
+}
+
+/// The message text split out of a composer file: an optional first line
+/// `Asunto:` / `Subject:` gives the subject, the rest is the body.
+fn split_subject(text: &str) -> (String, String) {
+    let text = text.trim_start_matches('\u{feff}');
+    let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+    for prefix in ["Asunto:", "Subject:"] {
+        if first.get(..prefix.len()).is_some_and(|p| p.eq_ignore_ascii_case(prefix)) {
+            return (String::from(first[prefix.len()..].trim()), String::from(rest));
+        }
+    }
+    (String::new(), String::from(text))
+}
+
+/// Shared implementation of the `mail` command. `mail send` reads the
+/// message from a file in the FAT directory `dir_cluster`.
+pub fn command_lines(args: &str, dir_cluster: u32, pump_ui: &mut impl FnMut()) -> Vec<String> {
+    let mut parts = args.split_whitespace();
+    let sub = parts.next().unwrap_or("status");
+    let a = parts.next();
+    let b = parts.next();
+    let mut out = Vec::new();
+
+    match (sub, a, b) {
+        ("status", _, _) => out.push(status_line()),
+        ("setup", Some(email), Some(password)) => match MailAccount::for_address(email, password) {
+            Some(account) => match save_account(&account) {
+                Ok(()) => {
+                    out.push(status_line());
+                    out.push(String::from("Ajusta servidores con: mail set <clave> <valor>"));
+                }
+                Err(e) => out.push(alloc::format!("Correo: {}", e)),
+            },
+            None => out.push(String::from("Correo: direccion invalida")),
+        },
+        ("set", Some(key), Some(_)) => {
+            // The value is everything after the key, so names may have spaces.
+            let value = args.trim_start()["set".len()..].trim_start()[key.len()..].trim();
+            let result = account().and_then(|mut account| {
+                account.set(key, value)?;
+                save_account(&account)
+            });
+            match result {
+                Ok(()) => out.push(status_line()),
+                Err(e) => out.push(alloc::format!("Correo: {}", e)),
+            }
+        }
+        ("show", _, _) => match load_account() {
+            Some(account) => {
+                for key in MAIL_ACCOUNT_KEYS.iter() {
+                    let value = if *key == "password" {
+                        String::from("********")
+                    } else {
+                        account.get(key).unwrap_or_default()
+                    };
+                    out.push(alloc::format!("  {:<14} {}", key, value));
+                }
+            }
+            None => out.push(status_line()),
+        },
+        ("inbox", count, _) => {
+            let count = count.and_then(|c| c.parse().ok()).unwrap_or(MAIL_INBOX_DEFAULT_COUNT);
+            match refresh_inbox(count, pump_ui) {
+                Ok(total) => {
+                    out.push(alloc::format!("INBOX: {} mensajes ({} mostrados)", total, MAIL_INBOX.lock().len()));
+                    out.extend(inbox_lines());
+                }
+                Err(e) => out.push(alloc::format!("Correo: {}", e)),
+            }
+        }
+        ("read", Some(index), _) => {
+            let uid = index
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| MAIL_INBOX.lock().get(i.checked_sub(1)?).map(|m| m.uid));
+            match uid {
+                Some(uid) => match fetch_message(uid, pump_ui) {
+                    Ok(message) => {
+                        out.push(alloc::format!("De: {}", message.from));
+                        out.push(alloc::format!("Para: {}", message.to));
+                        out.push(alloc::format!("Fecha: {}", message.date));
+                        out.push(alloc::format!("Asunto: {}", message.subject));
+                        out.push(String::new());
+                        out.extend(message.text.lines().map(String::from));
+                    }
+                    Err(e) => out.push(alloc::format!("Correo: {}", e)),
+                },
+                None => out.push(String::from("Correo: numero fuera de la lista (usa: mail inbox)")),
+            }
+        }
+        ("send", Some(to), Some(file)) => {
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+            let result = fat.read_file_in_dir(dir_cluster, file).map_err(String::from).and_then(|raw| {
+                let (subject, body) = split_subject(String::from_utf8_lossy(raw.as_slice()).as_ref());
+                send_message(to, subject.as_str(), body.as_str(), pump_ui)
+            });
+            match result {
+                Ok(()) => out.push(alloc::format!("Correo: enviado a {}", to)),
+                Err(e) => out.push(alloc::format!("Correo: {}", e)),
+            }
+        }
+        _ => out.push(String::from(
+            "Uso: mail [status|setup <email> <clave>|set <clave> <valor>|show|inbox [n]|read <n>|send <para> <archivo>]",
+        )),
+    }
+    out
+}
@@ -0,0 +1,227 @@
+//! mDNS service discovery (RFC 6762 / 6763), used to find IPP printers.
+//!
+//! Queries go out as "legacy unicast": from an ephemeral port to
+//! 224.0.0.251:5353, so responders answer straight back to that port and the
+//! interface never has to join the multicast group. Answers that leave out
+//! the SRV, TXT or A records of an instance are followed up with queries for
+//! exactly those until the timeout.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use smoltcp::socket::udp;
+use smoltcp::wire::{IpAddress, IpEndpoint};
+
+use redux_netparse::dns::{
+    build_dns_query, instance_label, parse_dns_message, DnsData, DnsRecord, DNS_TYPE_A, DNS_TYPE_PTR, DNS_TYPE_SRV,
+    DNS_TYPE_TXT, MDNS_GROUP, MDNS_PORT,
+};
+
+use crate::println;
+
+const MDNS_LOCAL_PORT_BASE: u16 = 49_400;
+const MDNS_REQUERY_MS: u64 = 1_000;
+const MDNS_RX_PACKETS: usize = 8;
+const MDNS_RX_BYTES: usize = 9_000;
+const MDNS_MAX_RECORDS: usize = 256;
+const MDNS_MAX_SERVICES: usize = 16;
+/// Questions per follow-up query, so it stays well inside one datagram.
+const MDNS_MAX_QUESTIONS: usize = 12;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MdnsService {
+    /// Instance label, e.g. "Oficina" for "Oficina._ipp._tcp.local".
+    pub name: String,
+    pub instance: String,
+    pub host: String,
+    pub addr: [u8; 4],
+    pub port: u16,
+    pub txt: Vec<String>,
+}
+
+impl MdnsService {
+    pub fn is_complete(&self) -> bool {
+        self.port != 0 && self.addr != [0; 4]
+    }
+}
+
+/// Every instance of `service` announced in `records`, with whatever SRV,
+/// TXT and A data is known for it so far. Goodbye records (TTL 0) are skipped.
+pub fn collect_services(service: &str, records: &[DnsRecord]) -> Vec<MdnsService> {
+    let mut out: Vec<MdnsService> = Vec::new();
+    for record in records.iter() {
+        let DnsData::Ptr(instance) = &record.data else {
+            continue;
+        };
+        if !record.name.eq_ignore_ascii_case(service)
+            || record.ttl == 0
+            || out.len() >= MDNS_MAX_SERVICES
+            || out.iter().any(|s| s.instance.eq_ignore_ascii_case(instance))
+        {
+            continue;
+        }
+        let mut found = MdnsService {
+            name: String::from(instance_label(instance, service)),
+            instance: instance.clone(),
+            host: String::new(),
+            addr: [0; 4],
+            port: 0,
+            txt: Vec::new(),
+        };
+        for r in records.iter().filter(|r| r.name.eq_ignore_ascii_case(instance)) {
+            match &r.data {
+                DnsData::Srv { port, target, .. } => {
+                    found.port = *port;
+                    found.host = target.clone();
+                }
+                DnsData::Txt(entries) => found.txt = entries.clone(),
+                _ => {}
+            }
+        }
+        found.addr = records
+            .iter()
+            .find_map(|r| match r.data {
+                DnsData::A(addr) if !found.host.is_empty() && r.name.eq_ignore_ascii_case(&found.host) => Some(addr),
+                _ => None,
+            })
+            .unwrap_or([0; 4]);
+        out.push(found);
+    }
+    out
+}
+
+/// The next query: the service PTR, plus SRV/TXT for instances without a
+/// port and A for hosts without an address.
+pub fn follow_up_questions(service: &str, found: &[MdnsService]) -> Vec<(String, u16)> {
+    let mut questions = alloc::vec![(String::from(service), DNS_TYPE_PTR)];
+    for s in found.iter() {
+        if s.port == 0 {
+            questions.push((s.instance.clone(), DNS_TYPE_SRV));
+            questions.push((s.instance.clone(), DNS_TYPE_TXT));
+        } else if s.addr == [0; 4] && !s.host.is_empty() {
+            questions.push((s.host.clone(), DNS_TYPE_A));
+        }
+    }
+    questions.truncate(MDNS_MAX_QUESTIONS);
+    questions
+}
+
+/// Browse for `service` (e.g. "_ipp._tcp.local") for up to `timeout_ms`.
+/// Returns early once every instance seen has an address and a port and a
+/// second query round found nothing new.
+pub fn browse(service: &str, timeout_ms: u64, pump_ui: &mut impl FnMut()) -> Vec<MdnsService> {
+    let _t = crate::trace::scope_with("net", "mdns", service);
+    unsafe {
+        if super::IFACE.get().is_none() || super::SOCKETS.get().is_none() {
+            println("Net: Stack not initialized.");
+            return Vec::new();
+        }
+        if super::get_ip_address().is_none() {
+            return Vec::new();
+        }
+        let iface = super::IFACE.get_mut().as_mut().unwrap();
+        let sockets = super::SOCKETS.get_mut().as_mut().unwrap();
+
+        let rx = udp::PacketBuffer::new(
+            alloc::vec![udp::PacketMetadata::EMPTY; MDNS_RX_PACKETS],
+            alloc::vec![0u8; MDNS_RX_BYTES],
+        );
+        let tx = udp::PacketBuffer::new(alloc::vec![udp::PacketMetadata::EMPTY; 2], alloc::vec![0u8; 2048]);
+        let mut socket = udp::Socket::new(rx, tx);
+        let local_port = MDNS_LOCAL_PORT_BASE + (crate::timer::ticks() % 500) as u16;
+        if socket.bind(local_port).is_err() {
+            return Vec::new();
+        }
+        let handle = sockets.add(socket);
+        let group = IpEndpoint::new(IpAddress::Ipv4(super::ipv4_from_octets(MDNS_GROUP)), MDNS_PORT);
+        let id = (crate::timer::ticks() as u16) | 1;
+
+        let mut records: Vec<DnsRecord> = Vec::new();
+        let mut seen_after_query = 0usize;
+        let deadline = crate::timer::Deadline::after_ms(timeout_ms);
+        let mut next_query = crate::timer::Deadline::after_ms(0);
+        let mut rounds = 0usize;
+        while !deadline.expired() {
+            if next_query.expired() {
+                let found = collect_services(service, &records);
+                if rounds >= 2
+                    && !found.is_empty()
+                    && found.iter().all(|s| s.is_complete())
+                    && records.len() == seen_after_query
+                {
+                    break;
+                }
+                seen_after_query = records.len();
+                let questions = follow_up_questions(service, &found);
+                let refs: Vec<(&str, u16)> = questions.iter().map(|(name, qtype)| (name.as_str(), *qtype)).collect();
+                if let Some(query) = build_dns_query(id, &refs, false) {
+                    let socket = sockets.get_mut::<udp::Socket>(handle);
+                    if socket.can_send() {
+                        let _ = socket.send_slice(&query, group);
+                    }
+                }
+                rounds += 1;
+                next_query = crate::timer::Deadline::after_ms(MDNS_REQUERY_MS);
+            }
+
+            pump_ui();
+            super::net_poll_blocking(iface, sockets);
+            let socket = sockets.get_mut::<udp::Socket>(handle);
+            while let Ok((payload, _)) = socket.recv() {
+                let Some(message) = parse_dns_message(payload) else {
+                    continue;
+                };
+                if !message.is_response {
+                    continue;
+                }
+                for record in message.records {
+                    if records.len() < MDNS_MAX_RECORDS && !records.contains(&record) {
+                        records.push(record);
+                    }
+                }
+            }
+            uefi::boot::stall(super::NET_BLOCKING_LOOP_STALL_US);
+        }
+        sockets.remove(handle);
+
+        let mut found = collect_services(service, &records);
+        found.retain(|s| s.is_complete());
+        found
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "net_mdns";
+
+    fn services_join_ptr_srv_txt_and_a() {
+        let record = |name: &str, data: DnsData| DnsRecord {
+            name: String::from(name),
+            rtype: 0,
+            ttl: 120,
+            data,
+        };
+        let mut records = alloc::vec![
+            record("_ipp._tcp.local", DnsData::Ptr(String::from("Oficina._ipp._tcp.local"))),
+            record("_IPP._tcp.local", DnsData::Ptr(String::from("Casa._ipp._tcp.local"))),
+            record(
+                "oficina._ipp._tcp.local",
+                DnsData::Srv { priority: 0, weight: 0, port: 631, target: String::from("print.local") },
+            ),
+            record("Oficina._ipp._tcp.local", DnsData::Txt(alloc::vec![String::from("rp=ipp/print")])),
+        ];
+        let found = collect_services("_ipp._tcp.local", &records);
+        crate::selftest::ensure_eq(found.len(), 2, "dos instancias")?;
+        crate::selftest::ensure_eq(found[0].name.as_str(), "Oficina", "nombre")?;
+        crate::selftest::ensure_eq((found[0].port, found[0].host.as_str()), (631, "print.local"), "srv")?;
+        crate::selftest::ensure(!found[0].is_complete(), "sin direccion")?;
+        let questions = follow_up_questions("_ipp._tcp.local", &found);
+        crate::selftest::ensure_eq(questions.len(), 4, "ptr, a, srv y txt")?;
+        crate::selftest::ensure_eq(questions[1].clone(), (String::from("print.local"), DNS_TYPE_A), "pide A")?;
+
+        records.push(record("PRINT.local", DnsData::A([192, 168, 1, 40])));
+        let found = collect_services("_ipp._tcp.local", &records);
+        crate::selftest::ensure_eq(found[0].addr, [192, 168, 1, 40], "direccion")?;
+        crate::selftest::ensure(found[0].is_complete(), "completa")?;
+        records[1].ttl = 0;
+        crate::selftest::ensure_eq(collect_services("_ipp._tcp.local", &records).len(), 1, "despedida")
+    }
+}
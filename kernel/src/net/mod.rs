@@ -3,20 +3,55 @@ use alloc::vec::Vec;
 use alloc::string::String;
 use alloc::format;
 use core::str;
+use core::sync::atomic::{AtomicU64, Ordering};
 use miniz_oxide::inflate::{decompress_to_vec, decompress_to_vec_zlib};
-use smoltcp::phy::{Device, DeviceCapabilities, Medium};
+use smoltcp::phy::{Checksum, Device, DeviceCapabilities, Medium};
 use smoltcp::time::Instant;
 use smoltcp::iface::{Interface, Config, SocketSet};
 use smoltcp::socket::{tcp, dhcpv4, dns};
-use smoltcp::wire::{EthernetAddress, IpCidr, Ipv4Address, IpAddress};
+use smoltcp::wire::{EthernetAddress, HardwareAddress, IpCidr, Ipv4Address, IpAddress};
 use smoltcp::iface::SocketStorage;
 
 use crate::println;
+use crate::spinlock::SpinLock;
+use crate::sync::KernelCell;
+use redux_netparse::{ascii_lowercase, starts_with_ignore_ascii_case};
+use redux_netparse::cookie::{
+    http_cookie_domain_matches, http_cookie_path_matches, http_parse_set_cookie, HttpCookieEntry,
+};
+use redux_netparse::hpack::{hpack_decode_header_block, hpack_encode_request_header, HpackDynamicTable};
+use redux_netparse::http::{
+    find_http_header_end, header_first, header_values, http_decode_chunked_body, interim_responses_len,
+    parse_http_headers, HttpMethod, ParsedHttpHeaders,
+};
+use redux_netparse::url::{extract_url_host, parse_url};
 pub mod tls;
+pub mod syslog;
+pub mod firewall;
+pub mod redux_http;
+pub mod gemini;
+pub mod gopher;
+pub mod ftp;
+pub mod mail;
+pub mod bench;
+pub mod request;
+pub mod pool;
+pub mod worker;
+pub mod dhcp;
+pub mod profile;
+pub mod portal;
+pub mod stats;
+pub mod arp;
+pub mod mtu;
+pub mod mdns;
+pub mod ipp;
+pub mod remote;
 
 const DHCP_STATUS_INACTIVE: &str = "Inactivo";
 const DHCP_STATUS_SEARCHING: &str = "Buscando...";
 const DHCP_STATUS_CONFIGURED: &str = "Configurado";
+/// Using the lease saved on disk until the server answers.
+const DHCP_STATUS_RESTORED: &str = "Configurado (lease guardado)";
 const DHCP_STATUS_NO_LINK: &str = "Sin enlace";
 const DHCP_STATUS_STATIC: &str = "IP Fija";
 const NET_TRANSPORT_NONE: &str = "Sin interfaz";
@@ -35,14 +70,23 @@ const HTTP_ACCEPT_ENCODING_VALUE: &str = "gzip, deflate, identity";
 const HTTP_CACHE_MAX_ENTRIES: usize = 16;
 const HTTP_CACHE_MAX_RESPONSE_BYTES: usize = 1024 * 1024;
 const HTTP_COOKIE_MAX_ENTRIES: usize = 64;
-const HTTP_CONN_POOL_MAX_ENTRIES: usize = 4;
-const HTTP_CONN_POOL_IDLE_TICKS: u64 = 3_000;
-const HTTP2_TLS_POOL_MAX_ENTRIES: usize = 2;
-const HTTP2_TLS_POOL_IDLE_TICKS: u64 = 4_000;
 const HTTP_RETRY_MAX_ATTEMPTS: usize = 3;
-const HTTP_RETRY_BASE_BACKOFF_TICKS: u64 = 25;
-const HTTP_RETRY_MAX_BACKOFF_TICKS: u64 = 800;
+const HTTP_RETRY_BASE_BACKOFF_MS: u64 = 250;
+const HTTP_RETRY_MAX_BACKOFF_MS: u64 = 8_000;
 const DNS_SERVER_LIMIT: usize = 1;
+const NET_SOCKET_STORAGE_SLOTS: usize = 14;
+pub const TCP_RX_KIB_KEY: &str = "net.tcp_rx_kib";
+pub const TCP_TX_KIB_KEY: &str = "net.tcp_tx_kib";
+const TCP_RX_DEFAULT_KIB: i64 = 256;
+const TCP_TX_DEFAULT_KIB: i64 = 32;
+const TCP_BUFFER_MIN_KIB: i64 = 4;
+const TCP_BUFFER_MAX_KIB: i64 = 4096;
+pub const TCP_KEEPALIVE_KEY: &str = "net.tcp_keepalive_s";
+pub const TCP_NODELAY_KEY: &str = "net.tcp_nodelay";
+/// Below the usual 2-5 minute NAT idle timeout.
+const TCP_KEEPALIVE_DEFAULT_S: i64 = 60;
+/// Timer ticks are 10 ms.
+const MS_PER_TICK: u64 = 10;
 
 // Default networking mode at boot.
 // `false` = start in DHCP mode automatically.
@@ -62,6 +106,9 @@ impl Device for ReduxPhy {
     type TxToken<'a> = ReduxTxToken<'a>;
 
     fn receive(&mut self, timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        if let Some(frame) = arp::take_injected() {
+            return self.transmit(timestamp).map(|tx| (ReduxRxToken::Injected(frame), tx));
+        }
         match self {
             Self::Virtio(v) => v.receive(timestamp).map(|(rx, tx)| (ReduxRxToken::Virtio(rx), ReduxTxToken::Virtio(tx))),
             Self::Intel(i) => i.receive(timestamp).map(|(rx, tx)| (ReduxRxToken::Intel(rx), ReduxTxToken::Intel(tx))),
@@ -83,9 +130,19 @@ impl Device for ReduxPhy {
     }
 }
 
+/// Bytes handed to/from smoltcp since boot, for the performance HUD.
+static RX_BYTES: AtomicU64 = AtomicU64::new(0);
+static TX_BYTES: AtomicU64 = AtomicU64::new(0);
+
+pub fn traffic_bytes() -> (u64, u64) {
+    (RX_BYTES.load(Ordering::Relaxed), TX_BYTES.load(Ordering::Relaxed))
+}
+
 pub enum ReduxRxToken<'a> {
     Virtio(VirtioRxToken),
     Intel(crate::intel_net::IntelRxToken),
+    /// A frame the stack feeds itself (static ARP entries); never on the wire.
+    Injected(Vec<u8>),
     _Dummy(&'a ()),
 }
 
@@ -94,9 +151,23 @@ impl<'a> smoltcp::phy::RxToken for ReduxRxToken<'a> {
     where
         F: FnOnce(&mut [u8]) -> R,
     {
+        let iface = match self {
+            Self::Virtio(_) => Some(stats::Iface::Virtio),
+            Self::Intel(_) => Some(stats::Iface::Intel),
+            _ => None,
+        };
+        let f = |buf: &mut [u8]| {
+            if let Some(iface) = iface {
+                RX_BYTES.fetch_add(buf.len() as u64, Ordering::Relaxed);
+                stats::count_rx(iface, buf.len());
+                arp::observe(buf);
+            }
+            f(buf)
+        };
         match self {
             Self::Virtio(rx) => rx.consume(f),
             Self::Intel(rx) => rx.consume(f),
+            Self::Injected(mut frame) => f(&mut frame),
             _ => unsafe { core::hint::unreachable_unchecked() },
         }
     }
@@ -113,6 +184,17 @@ impl<'a> smoltcp::phy::TxToken for ReduxTxToken<'a> {
     where
         F: FnOnce(&mut [u8]) -> R,
     {
+        TX_BYTES.fetch_add(len as u64, Ordering::Relaxed);
+        let iface = match self {
+            Self::Virtio(_) => stats::Iface::Virtio,
+            _ => stats::Iface::Intel,
+        };
+        stats::count_tx(iface, len);
+        let f = |buf: &mut [u8]| {
+            let result = f(buf);
+            mtu::clamp_outgoing(iface, buf);
+            result
+        };
         match self {
             Self::Virtio(tx) => tx.consume(len, f),
             Self::Intel(tx) => tx.consume(len, f),
@@ -128,14 +210,8 @@ impl Device for VirtioPhy {
     type TxToken<'a> = VirtioTxToken where Self: 'a;
 
     fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
-        unsafe {
-            if let Some(ref mut drv) = crate::virtio::net::GLOBAL_NET {
-                if let Some(packet) = drv.receive() {
-                    return Some((VirtioRxToken(packet), VirtioTxToken));
-                }
-            }
-        }
-        None
+        let packet = crate::virtio::net::GLOBAL_NET.lock().as_mut()?.receive()?;
+        Some((VirtioRxToken(packet), VirtioTxToken))
     }
 
     fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
@@ -144,8 +220,23 @@ impl Device for VirtioPhy {
 
     fn capabilities(&self) -> DeviceCapabilities {
         let mut caps = DeviceCapabilities::default();
-        caps.max_transmission_unit = 1500;
+        let (mtu, tx_offload, rx_offload) = crate::virtio::net::GLOBAL_NET
+            .lock()
+            .as_ref()
+            .map(|drv| (drv.mtu(), drv.tx_checksum_offload(), drv.rx_checksum_offload()))
+            .unwrap_or((1500, false, false));
+        // smoltcp counts the Ethernet header as part of the MTU.
+        caps.max_transmission_unit = mtu + 14;
         caps.medium = Medium::Ethernet;
+        // TCP/UDP checksums the device computes (TX) or validates (RX) are skipped in smoltcp.
+        let l4 = match (tx_offload, rx_offload) {
+            (true, true) => Checksum::None,
+            (true, false) => Checksum::Rx,
+            (false, true) => Checksum::Tx,
+            (false, false) => Checksum::Both,
+        };
+        caps.checksum.tcp = l4;
+        caps.checksum.udp = l4;
         caps
     }
 }
@@ -171,35 +262,86 @@ impl smoltcp::phy::TxToken for VirtioTxToken {
     {
         let mut buffer = alloc::vec![0u8; len];
         let result = f(&mut buffer);
-        unsafe {
-            if let Some(ref mut drv) = crate::virtio::net::GLOBAL_NET {
-                drv.transmit(&buffer);
-            }
+        if let Some(drv) = crate::virtio::net::GLOBAL_NET.lock().as_mut() {
+            drv.transmit(&buffer);
         }
         result
     }
 }
 
-// Global Stack State
-pub static mut SOCKETS: Option<SocketSet<'static>> = None;
-pub static mut IFACE: Option<Interface> = None;
-pub static mut DHCP_HANDLE: Option<smoltcp::iface::SocketHandle> = None;
-pub static mut DNS_HANDLE: Option<smoltcp::iface::SocketHandle> = None;
-pub static mut DHCP_STATUS: &str = DHCP_STATUS_INACTIVE;
-pub static mut IPV4_GATEWAY: Option<IpAddress> = None;
-pub static mut ACTIVE_TRANSPORT: &str = NET_TRANSPORT_NONE;
-pub static mut FAILOVER_POLICY: &str = FAILOVER_ETHERNET_FIRST;
-static mut USE_STATIC_IPV4_RUNTIME: bool = NET_USE_STATIC_IPV4;
-static mut STATIC_IPV4_ADDR_RUNTIME: [u8; 4] = STATIC_IPV4_ADDR;
-static mut STATIC_IPV4_PREFIX_RUNTIME: u8 = STATIC_IPV4_PREFIX_LEN;
-static mut STATIC_IPV4_GATEWAY_RUNTIME: [u8; 4] = STATIC_IPV4_GATEWAY;
-static mut STATIC_DNS_SERVERS_RUNTIME: [[u8; 4]; 2] = STATIC_DNS_SERVERS;
-static mut HTTPS_PROXY_ENABLED: bool = false;
-static mut DHCP_LAST_RESET_TICK: u64 = 0;
-static mut WIFI_AUTOCONNECT_LAST_TICK: u64 = 0;
-static mut HTTP_CACHE: Vec<HttpCacheEntry> = Vec::new();
-static mut HTTP_COOKIE_JAR: Vec<HttpCookieEntry> = Vec::new();
-static mut HTTP_CONN_POOL: Vec<HttpConnPoolEntry> = Vec::new();
+// Global Stack State. smoltcp is driven from the kernel thread only and its
+// borrows span whole requests, so the stack lives in kernel cells; the small
+// link state below is read from everywhere and sits behind a lock.
+pub static SOCKETS: KernelCell<Option<SocketSet<'static>>> = KernelCell::new(None);
+pub static IFACE: KernelCell<Option<Interface>> = KernelCell::new(None);
+pub static DHCP_HANDLE: KernelCell<Option<smoltcp::iface::SocketHandle>> = KernelCell::new(None);
+pub static DNS_HANDLE: KernelCell<Option<smoltcp::iface::SocketHandle>> = KernelCell::new(None);
+
+struct LinkState {
+    dhcp_status: &'static str,
+    gateway: Option<IpAddress>,
+    active_transport: &'static str,
+    failover_policy: &'static str,
+    use_static_ipv4: bool,
+    static_ipv4_addr: [u8; 4],
+    static_ipv4_prefix: u8,
+    static_ipv4_gateway: [u8; 4],
+    static_dns_servers: [[u8; 4]; 2],
+    https_proxy_enabled: bool,
+    /// DNS servers forced by the active network profile; empty leaves DHCP
+    /// or the static defaults in charge.
+    dns_override: Vec<[u8; 4]>,
+    wifi_autoconnect_last_tick: u64,
+}
+
+static LINK: SpinLock<LinkState> = SpinLock::new(LinkState {
+    dhcp_status: DHCP_STATUS_INACTIVE,
+    gateway: None,
+    active_transport: NET_TRANSPORT_NONE,
+    failover_policy: FAILOVER_ETHERNET_FIRST,
+    use_static_ipv4: NET_USE_STATIC_IPV4,
+    static_ipv4_addr: STATIC_IPV4_ADDR,
+    static_ipv4_prefix: STATIC_IPV4_PREFIX_LEN,
+    static_ipv4_gateway: STATIC_IPV4_GATEWAY,
+    static_dns_servers: STATIC_DNS_SERVERS,
+    https_proxy_enabled: false,
+    dns_override: Vec::new(),
+    wifi_autoconnect_last_tick: 0,
+});
+
+pub fn dhcp_status() -> &'static str {
+    LINK.lock().dhcp_status
+}
+
+fn set_dhcp_status(status: &'static str) {
+    let previous = core::mem::replace(&mut LINK.lock().dhcp_status, status);
+    if previous != status {
+        if status == DHCP_STATUS_SEARCHING {
+            dhcp::arm_retry();
+        } else if previous == DHCP_STATUS_SEARCHING {
+            dhcp::cancel_retry();
+        }
+    }
+}
+
+fn set_gateway(gateway: Option<IpAddress>) {
+    LINK.lock().gateway = gateway;
+}
+
+fn use_static_ipv4() -> bool {
+    LINK.lock().use_static_ipv4
+}
+
+fn static_ipv4_runtime() -> (Ipv4Address, u8, Ipv4Address) {
+    let link = LINK.lock();
+    (
+        ipv4_from_octets(link.static_ipv4_addr),
+        link.static_ipv4_prefix,
+        ipv4_from_octets(link.static_ipv4_gateway),
+    )
+}
+static HTTP_CACHE: SpinLock<Vec<HttpCacheEntry>> = SpinLock::new(Vec::new());
+static HTTP_COOKIE_JAR: SpinLock<Vec<HttpCookieEntry>> = SpinLock::new(Vec::new());
 
 #[derive(Clone)]
 struct HttpCacheEntry {
@@ -210,40 +352,13 @@ struct HttpCacheEntry {
     stored_at_ticks: u64,
 }
 
-#[derive(Clone)]
-struct HttpCookieEntry {
-    name: String,
-    value: String,
-    domain: String,
-    path: String,
-    secure: bool,
-    host_only: bool,
-    expires_at_ticks: Option<u64>,
-}
-
 #[derive(Clone, Default)]
 struct HttpRequestHints {
     cookie_header: Option<String>,
     if_none_match: Option<String>,
     if_modified_since: Option<String>,
-}
-
-#[derive(Clone, Default)]
-struct ParsedHttpHeaders {
-    status_code: Option<u16>,
-    status_line: Option<String>,
-    headers: Vec<(String, String)>,
-    body_offset: usize,
-}
-
-#[derive(Clone)]
-struct HttpConnPoolEntry {
-    handle: smoltcp::iface::SocketHandle,
-    host: String,
-    port: u16,
-    is_https: bool,
-    use_https_proxy: bool,
-    last_used_ticks: u64,
+    /// Caller-supplied headers (`redux_http`), already validated.
+    extra_headers: Vec<(String, String)>,
 }
 
 fn default_ethernet_transport() -> &'static str {
@@ -257,32 +372,53 @@ fn default_ethernet_transport() -> &'static str {
 fn refresh_active_transport() {
     let ethernet_up = if crate::intel_net::get_model_name().is_some() {
         crate::intel_net::is_link_up()
+    } else if crate::virtio::net::is_present() {
+        crate::virtio::net::is_link_up()
     } else {
         true
     };
     let wifi_up = crate::intel_wifi::is_data_path_ready() && crate::intel_wifi::is_connected();
     let ethernet_transport = default_ethernet_transport();
 
-    let selected = unsafe {
-        if FAILOVER_POLICY == FAILOVER_WIFI_FIRST {
-            if wifi_up {
-                NET_TRANSPORT_INTEL_WIFI
-            } else if ethernet_up {
-                ethernet_transport
-            } else {
-                NET_TRANSPORT_NONE
-            }
+    let selected = if get_failover_policy() == FAILOVER_WIFI_FIRST {
+        if wifi_up {
+            NET_TRANSPORT_INTEL_WIFI
         } else if ethernet_up {
             ethernet_transport
-        } else if wifi_up {
-            NET_TRANSPORT_INTEL_WIFI
         } else {
             NET_TRANSPORT_NONE
         }
+    } else if ethernet_up {
+        ethernet_transport
+    } else if wifi_up {
+        NET_TRANSPORT_INTEL_WIFI
+    } else {
+        NET_TRANSPORT_NONE
     };
 
-    unsafe {
-        ACTIVE_TRANSPORT = selected;
+    let previous = core::mem::replace(&mut LINK.lock().active_transport, selected);
+    if previous != selected {
+        notify_transport_lost(previous, selected);
+        if selected != NET_TRANSPORT_NONE {
+            profile::link_changed();
+            portal::network_changed();
+        }
+    }
+}
+
+/// Desktop notification when the active link goes away. Gaining a link is
+/// not announced; that happens on every boot.
+fn notify_transport_lost(previous: &str, selected: &str) {
+    use crate::gui::notifications::{post, Urgency};
+    let fallback = if selected == NET_TRANSPORT_NONE {
+        String::from("Sin conexion de red")
+    } else {
+        format!("Usando {}", selected)
+    };
+    if previous == NET_TRANSPORT_INTEL_WIFI {
+        post("red", "WiFi desconectado", fallback.as_str(), Urgency::Warning);
+    } else if previous != NET_TRANSPORT_NONE && selected == NET_TRANSPORT_NONE {
+        post("red", "Red desconectada", format!("{} sin enlace", previous).as_str(), Urgency::Warning);
     }
 }
 
@@ -317,19 +453,49 @@ fn reset_ipv4_runtime(iface: &mut Interface) {
         ip_addrs.push(cidr).unwrap();
     });
     let _ = iface.routes_mut().remove_default_ipv4_route();
+    set_gateway(None);
+}
+
+fn new_interface(hardware_addr: HardwareAddress, phy: &mut ReduxPhy, now: Instant) -> Interface {
+    let mut config = Config::new(hardware_addr);
+    // smoltcp derives TCP initial sequence numbers and DHCP XIDs from it.
+    config.random_seed = crate::random::next_u64();
+    Interface::new(config, phy, now)
+}
+
+/// smoltcp reads the device MTU only when the interface is built: build a
+/// new one with the same addresses and default route.
+fn rebuild_interface() {
     unsafe {
-        IPV4_GATEWAY = None;
+        let Some(old) = IFACE.get().as_ref() else {
+            return;
+        };
+        let addrs: Vec<IpCidr> = old.ip_addrs().to_vec();
+        let hardware_addr = old.hardware_addr();
+        let mut phy = if crate::intel_net::is_present() {
+            ReduxPhy::Intel(crate::intel_net::IntelPhy)
+        } else {
+            ReduxPhy::Virtio(VirtioPhy)
+        };
+        let now = Instant::from_millis(crate::timer::ticks() as i64 * 10);
+        let mut iface = new_interface(hardware_addr, &mut phy, now);
+        iface.update_ip_addrs(|ip_addrs| {
+            ip_addrs.clear();
+            for cidr in addrs {
+                let _ = ip_addrs.push(cidr);
+            }
+        });
+        if let Some(IpAddress::Ipv4(gateway)) = get_gateway() {
+            let _ = iface.routes_mut().add_default_ipv4_route(gateway);
+        }
+        *IFACE.get_mut() = Some(iface);
     }
+    println("Net: interfaz reconstruida con la nueva MTU.");
+    arp::announce();
 }
 
 fn apply_static_ipv4_runtime(iface: &mut Interface) {
-    let (static_ip, prefix, gateway) = unsafe {
-        (
-            ipv4_from_octets(STATIC_IPV4_ADDR_RUNTIME),
-            STATIC_IPV4_PREFIX_RUNTIME,
-            ipv4_from_octets(STATIC_IPV4_GATEWAY_RUNTIME),
-        )
-    };
+    let (static_ip, prefix, gateway) = static_ipv4_runtime();
 
     iface.update_ip_addrs(|ip_addrs| {
         ip_addrs.clear();
@@ -340,17 +506,20 @@ fn apply_static_ipv4_runtime(iface: &mut Interface) {
     if iface.routes_mut().add_default_ipv4_route(gateway.into()).is_err() {
         println("Net: Static gateway route update failed.");
     }
-    unsafe {
-        IPV4_GATEWAY = Some(gateway.into());
-    }
+    set_gateway(Some(gateway.into()));
+    arp::announce();
 }
 
 fn static_dns_servers_runtime() -> Vec<IpAddress> {
     let mut dns_servers = Vec::with_capacity(2);
-    unsafe {
-        for server in STATIC_DNS_SERVERS_RUNTIME.iter() {
-            dns_servers.push(ipv4_from_octets(*server).into());
-        }
+    let link = LINK.lock();
+    let servers: &[[u8; 4]] = if link.dns_override.is_empty() {
+        &link.static_dns_servers
+    } else {
+        &link.dns_override
+    };
+    for server in servers.iter() {
+        dns_servers.push(ipv4_from_octets(*server).into());
     }
     dns_servers
 }
@@ -386,17 +555,14 @@ fn maybe_autoconnect_wifi(now_ticks: u64, ethernet_up: bool) {
         return;
     }
 
-    let wifi_first = unsafe { FAILOVER_POLICY == FAILOVER_WIFI_FIRST };
+    let wifi_first = get_failover_policy() == FAILOVER_WIFI_FIRST;
     let should_use_wifi = wifi_first || !ethernet_up;
 
     if should_use_wifi {
-        unsafe {
-            if !crate::intel_wifi::is_connected()
-                && now_ticks.saturating_sub(WIFI_AUTOCONNECT_LAST_TICK) >= 300
-            {
-                let _ = crate::intel_wifi::connect_profile();
-                WIFI_AUTOCONNECT_LAST_TICK = now_ticks;
-            }
+        let last_tick = LINK.lock().wifi_autoconnect_last_tick;
+        if !crate::intel_wifi::is_connected() && now_ticks.saturating_sub(last_tick) >= 300 {
+            let _ = crate::intel_wifi::connect_profile();
+            LINK.lock().wifi_autoconnect_last_tick = now_ticks;
         }
     } else if crate::intel_wifi::is_connected() {
         let _ = crate::intel_wifi::disconnect();
@@ -406,7 +572,7 @@ fn maybe_autoconnect_wifi(now_ticks: u64, ethernet_up: bool) {
 pub fn init() {
     println("Net: Initializing Stack...");
     
-    let mut phy = if unsafe { crate::intel_net::GLOBAL_INTEL_NET.is_some() } {
+    let mut phy = if crate::intel_net::is_present() {
         println("Net: Using Native Intel I225/I226 PHY.");
         ReduxPhy::Intel(crate::intel_net::IntelPhy)
     } else {
@@ -427,25 +593,25 @@ pub fn init() {
     let mut mac = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
     if let Some(intel_mac) = crate::intel_net::get_mac_address() {
         mac = intel_mac;
-    } else if let Some(virtio_mac) = unsafe { crate::virtio::net::GLOBAL_NET.as_ref().map(|drv| drv.mac_address()) } {
+    } else if let Some(virtio_mac) = crate::virtio::net::mac_address() {
         mac = virtio_mac;
     }
-    let mut config = Config::new(EthernetAddress(mac).into());
-    config.random_seed = 0x12345678;
-    
-    let mut iface = Interface::new(config, &mut phy, Instant::from_millis(0));
+    arp::set_local_mac(mac);
+    mtu::apply_configured();
+    let mut iface = new_interface(EthernetAddress(mac).into(), &mut phy, Instant::from_millis(0));
     
     // Start from 0.0.0.0 and wait for DHCP lease.
     reset_ipv4_runtime(&mut iface);
 
     // Pre-allocate socket storage
-    let mut storage = alloc::vec::Vec::with_capacity(8);
-    for _ in 0..8 { storage.push(SocketStorage::EMPTY); }
+    let mut storage = alloc::vec::Vec::with_capacity(NET_SOCKET_STORAGE_SLOTS);
+    for _ in 0..NET_SOCKET_STORAGE_SLOTS { storage.push(SocketStorage::EMPTY); }
     let storage_static = alloc::boxed::Box::leak(storage.into_boxed_slice());
     let mut sockets = SocketSet::new(&mut storage_static[..]);
 
-    let dhcp = dhcpv4::Socket::new();
-    let dhcp_handle = Some(sockets.add(dhcp));
+    let mut dhcp_socket = dhcpv4::Socket::new();
+    dhcp::configure_socket(&mut dhcp_socket);
+    let dhcp_handle = Some(sockets.add(dhcp_socket));
 
     let dns_servers = alloc::vec![Ipv4Address::new(8, 8, 8, 8).into()];
     let dns_servers_static: &'static [IpAddress] = alloc::boxed::Box::leak(dns_servers.into_boxed_slice());
@@ -457,35 +623,31 @@ pub fn init() {
     let dns_socket = dns::Socket::new(dns_servers_static, &mut queries_static[..]); 
     let dns_handle = sockets.add(dns_socket);
 
-    let startup_status = unsafe {
-        if USE_STATIC_IPV4_RUNTIME {
-            apply_static_ipv4_runtime(&mut iface);
-            let static_dns = static_dns_servers_runtime();
-            update_dns_servers(&mut sockets, dns_handle, &static_dns);
-            DHCP_STATUS_STATIC
+    let startup_status = if use_static_ipv4() {
+        apply_static_ipv4_runtime(&mut iface);
+        let static_dns = static_dns_servers_runtime();
+        update_dns_servers(&mut sockets, dns_handle, &static_dns);
+        DHCP_STATUS_STATIC
+    } else {
+        let fallback_dns = default_dns_servers();
+        update_dns_servers(&mut sockets, dns_handle, &fallback_dns);
+        if dhcp::restore_saved_lease(&mut iface, &mut sockets, dns_handle) {
+            DHCP_STATUS_RESTORED
         } else {
-            let fallback_dns = default_dns_servers();
-            update_dns_servers(&mut sockets, dns_handle, &fallback_dns);
             DHCP_STATUS_SEARCHING
         }
     };
     
     unsafe {
-        DHCP_HANDLE = dhcp_handle;
-        DNS_HANDLE = Some(dns_handle);
-        IFACE = Some(iface);
-        SOCKETS = Some(sockets);
-        DHCP_STATUS = startup_status;
-    }
-
-    if unsafe { USE_STATIC_IPV4_RUNTIME } {
-        let (static_ip, prefix, gateway) = unsafe {
-            (
-                ipv4_from_octets(STATIC_IPV4_ADDR_RUNTIME),
-                STATIC_IPV4_PREFIX_RUNTIME,
-                ipv4_from_octets(STATIC_IPV4_GATEWAY_RUNTIME),
-            )
-        };
+        *DHCP_HANDLE.get_mut() = dhcp_handle;
+        *DNS_HANDLE.get_mut() = Some(dns_handle);
+        *IFACE.get_mut() = Some(iface);
+        *SOCKETS.get_mut() = Some(sockets);
+    }
+    set_dhcp_status(startup_status);
+
+    if use_static_ipv4() {
+        let (static_ip, prefix, gateway) = static_ipv4_runtime();
         println("Net: Initialized (Static IPv4 mode).");
         println(alloc::format!("Net: IP fija -> {}/{}", static_ip, prefix).as_str());
         println(alloc::format!("Net: Gateway -> {}", gateway).as_str());
@@ -495,9 +657,11 @@ pub fn init() {
 }
 
 pub fn poll() {
+    profile::apply_pending();
+    mtu::apply_pending();
     unsafe {
-        if let (Some(iface), Some(sockets)) = (&mut IFACE, &mut SOCKETS) {
-            let ethernet_up = if crate::intel_net::GLOBAL_INTEL_NET.is_some() {
+        if let (Some(iface), Some(sockets)) = (IFACE.get_mut(), SOCKETS.get_mut()) {
+            let ethernet_up = if crate::intel_net::is_present() {
                 crate::intel_net::is_link_up()
             } else {
                 true
@@ -507,48 +671,75 @@ pub fn poll() {
             maybe_autoconnect_wifi(now_ticks, ethernet_up);
             refresh_active_transport();
 
-            let mut phy = if crate::intel_net::GLOBAL_INTEL_NET.is_some() {
+            let mut phy = if crate::intel_net::is_present() {
                 ReduxPhy::Intel(crate::intel_net::IntelPhy)
             } else {
                 ReduxPhy::Virtio(VirtioPhy)
             };
 
             let timestamp = Instant::from_millis(now_ticks as i64 * 10);
+            mtu::note_local_net(iface);
             iface.poll(timestamp, &mut phy, sockets);
+            syslog::pump(iface, sockets, now_ticks);
+            worker::pump(iface, sockets, now_ticks);
+            portal::pump(now_ticks);
+            remote::pump(sockets, now_ticks);
+            arp::pump(iface, &mut phy, timestamp, now_ticks);
 
-            let active_transport = ACTIVE_TRANSPORT;
+            let active_transport = get_active_transport();
             if active_transport == NET_TRANSPORT_NONE {
-                DHCP_STATUS = DHCP_STATUS_NO_LINK;
+                set_dhcp_status(DHCP_STATUS_NO_LINK);
                 return;
             }
 
             if active_transport == NET_TRANSPORT_INTEL_ETH
-                && crate::intel_net::GLOBAL_INTEL_NET.is_some()
+                && crate::intel_net::is_present()
                 && !ethernet_up
             {
-                DHCP_STATUS = DHCP_STATUS_NO_LINK;
+                set_dhcp_status(DHCP_STATUS_NO_LINK);
                 return;
             }
 
-            if USE_STATIC_IPV4_RUNTIME {
-                DHCP_STATUS = DHCP_STATUS_STATIC;
+            if use_static_ipv4() {
+                set_dhcp_status(DHCP_STATUS_STATIC);
                 return;
             }
             
             // Background DHCP Management
-            if let Some(dhcp_handle) = DHCP_HANDLE {
-                if DHCP_STATUS == DHCP_STATUS_INACTIVE
-                    || DHCP_STATUS == DHCP_STATUS_NO_LINK
-                    || DHCP_STATUS == DHCP_STATUS_STATIC
+            if let Some(dhcp_handle) = *DHCP_HANDLE.get() {
+                let status = dhcp_status();
+                if status == DHCP_STATUS_INACTIVE
+                    || status == DHCP_STATUS_NO_LINK
+                    || status == DHCP_STATUS_STATIC
                 {
-                    DHCP_STATUS = DHCP_STATUS_SEARCHING;
+                    // Back from a link loss: keep a lease that is still
+                    // good (the socket renews it at T1), else start over.
+                    if dhcp::has_lease(now_ticks) {
+                        set_dhcp_status(DHCP_STATUS_CONFIGURED);
+                        portal::network_changed();
+                        arp::announce();
+                    } else {
+                        set_dhcp_status(DHCP_STATUS_SEARCHING);
+                        sockets.get_mut::<dhcpv4::Socket>(dhcp_handle).reset();
+                    }
+                } else if status == DHCP_STATUS_RESTORED && !dhcp::has_lease(now_ticks) {
+                    println("Net: DHCP saved lease expired.");
+                    set_dhcp_status(DHCP_STATUS_SEARCHING);
+                    dhcp::on_deconfigured(now_ticks);
+                    reset_ipv4_runtime(iface);
+                } else if status == DHCP_STATUS_SEARCHING && dhcp::take_retry_due() {
+                    println("Net: DHCP no answer, restarting discovery.");
+                    sockets.get_mut::<dhcpv4::Socket>(dhcp_handle).reset();
                 }
                 let event = sockets.get_mut::<dhcpv4::Socket>(dhcp_handle).poll();
                 if let Some(event) = event {
                     match event {
                         dhcpv4::Event::Configured(config) => {
                             println("Net: DHCP Configured!");
-                            DHCP_STATUS = DHCP_STATUS_CONFIGURED;
+                            if dhcp_status() != DHCP_STATUS_CONFIGURED {
+                                portal::network_changed();
+                            }
+                            set_dhcp_status(DHCP_STATUS_CONFIGURED);
                             println(alloc::format!("Net: IP -> {}", config.address).as_str());
                             
                             iface.update_ip_addrs(|addrs| {
@@ -556,6 +747,7 @@ pub fn poll() {
                                 let cidr: IpCidr = IpCidr::Ipv4(config.address);
                                 addrs.push(cidr).unwrap();
                             });
+                            arp::announce();
 
                             let _ = iface.routes_mut().remove_default_ipv4_route();
                             if let Some(router) = config.router {
@@ -563,40 +755,39 @@ pub fn poll() {
                                 if iface.routes_mut().add_default_ipv4_route(router.into()).is_err() {
                                     println("Net: DHCP Gateway route update failed.");
                                 }
-                                IPV4_GATEWAY = Some(router.into());
+                                set_gateway(Some(router.into()));
                             } else {
-                                IPV4_GATEWAY = None;
+                                set_gateway(None);
                             }
 
                             // Update DNS servers from DHCP
-                            if let Some(dns_handle) = DNS_HANDLE {
+                            if let Some(dns_handle) = *DNS_HANDLE.get() {
                                 let mut servers = alloc::vec::Vec::new();
                                 for server in config.dns_servers.iter() {
                                     if !server.is_unspecified() {
                                         servers.push((*server).into());
                                     }
                                 }
+                                let dns_override = get_dns_override();
+                                if !dns_override.is_empty() {
+                                    servers = dns_override.iter().map(|ip| IpAddress::from(ipv4_from_octets(*ip))).collect();
+                                }
                                 if servers.is_empty() {
                                     servers.push(Ipv4Address::new(8, 8, 8, 8).into()); // Fallback to Google
                                 }
                                 update_dns_servers(sockets, dns_handle, &servers);
                                 println("Net: DNS Servers Updated.");
                             }
+                            dhcp::on_configured(&config, now_ticks);
                         }
                         dhcpv4::Event::Deconfigured => {
                             println("Net: DHCP lease lost, retrying...");
-                            DHCP_STATUS = DHCP_STATUS_SEARCHING;
+                            set_dhcp_status(DHCP_STATUS_SEARCHING);
+                            dhcp::on_deconfigured(now_ticks);
                             reset_ipv4_runtime(iface);
                         }
                     }
                 }
-
-                if DHCP_STATUS == DHCP_STATUS_SEARCHING {
-                    if now_ticks.saturating_sub(DHCP_LAST_RESET_TICK) > 2000 {
-                        sockets.get_mut::<dhcpv4::Socket>(dhcp_handle).reset();
-                        DHCP_LAST_RESET_TICK = now_ticks;
-                    }
-                }
             }
         }
     }
@@ -604,12 +795,11 @@ pub fn poll() {
 
 const NET_BLOCKING_LOOP_STALL_US: usize = 1_000;
 const NET_BLOCKING_TIMEOUT_TICKS: u64 = 5_000;
-
-fn starts_with_ignore_ascii_case(text: &str, prefix: &str) -> bool {
-    text.get(..prefix.len())
-        .map(|head| head.eq_ignore_ascii_case(prefix))
-        .unwrap_or(false)
-}
+const NET_BLOCKING_TIMEOUT_MS: u64 = 50_000;
+const TLS_MAX_RECORD_PLAINTEXT: usize = 16 * 1024;
+/// Plaintext per write; with TLS framing it still fits the 4 KiB socket buffer.
+const TCP_SEND_CHUNK: usize = 2048;
+const TLS_RECORD_OVERHEAD: usize = 256;
 
 fn build_https_proxy_url(url: &str) -> String {
     let mut out = String::from(HTTPS_PROXY_BASE);
@@ -617,75 +807,25 @@ fn build_https_proxy_url(url: &str) -> String {
     out
 }
 
-fn extract_url_host(url: &str) -> Option<&str> {
-    let without_scheme = if starts_with_ignore_ascii_case(url, "http://") {
-        &url[7..]
-    } else if starts_with_ignore_ascii_case(url, "https://") {
-        &url[8..]
-    } else {
-        url
-    };
-
-    let authority = match without_scheme.find('/') {
-        Some(idx) => &without_scheme[..idx],
-        None => without_scheme,
-    };
-    let host = match authority.find(':') {
-        Some(idx) => &authority[..idx],
-        None => authority,
-    };
-
-    if host.is_empty() {
-        None
-    } else {
-        Some(host)
-    }
-}
-
 fn is_https_proxy_url(url: &str) -> bool {
     extract_url_host(url)
         .map(|host| host.eq_ignore_ascii_case(HTTPS_PROXY_HOST))
         .unwrap_or(false)
 }
 
-fn parse_url(url: &str) -> Option<(String, u16, &str)> {
-    let url = if starts_with_ignore_ascii_case(url, "http://") {
-        &url[7..]
-    } else if starts_with_ignore_ascii_case(url, "https://") {
-        &url[8..]
-    } else {
-        url
-    };
-    
-    let (addr_part, path) = match url.find('/') {
-        Some(idx) => (&url[..idx], &url[idx..]),
-        None => (url, "/"),
-    };
-    
-    let (host, port) = match addr_part.find(':') {
-        Some(idx) => (String::from(&addr_part[..idx]), addr_part[idx+1..].parse().ok()?),
-        None => (String::from(addr_part), 80),
-    };
-    
-    Some((host, port, path))
-}
-
-fn http_wait_ticks_with_ui(pump_ui: &mut impl FnMut(), wait_ticks: u64) {
-    if wait_ticks == 0 {
-        return;
-    }
-    let start = crate::timer::ticks();
-    while crate::timer::ticks().saturating_sub(start) < wait_ticks {
+fn http_wait_ms_with_ui(pump_ui: &mut impl FnMut(), wait_ms: u64) {
+    let deadline = crate::timer::Deadline::after_ms(wait_ms);
+    while !deadline.expired() {
         pump_ui();
         crate::timer::on_tick();
         uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
     }
 }
 
-fn http_retry_backoff_ticks(attempt: usize) -> u64 {
+fn http_retry_backoff_ms(attempt: usize) -> u64 {
     let shift = core::cmp::min(attempt as u32, 6);
-    let ticks = HTTP_RETRY_BASE_BACKOFF_TICKS.saturating_mul(1u64 << shift);
-    core::cmp::min(ticks, HTTP_RETRY_MAX_BACKOFF_TICKS)
+    let ms = HTTP_RETRY_BASE_BACKOFF_MS.saturating_mul(1u64 << shift);
+    core::cmp::min(ms, HTTP_RETRY_MAX_BACKOFF_MS)
 }
 
 fn http_should_retry_status(status: u16) -> bool {
@@ -713,521 +853,142 @@ fn http_response_allows_keepalive(parsed: &ParsedHttpHeaders) -> bool {
     }
 }
 
-fn http_pool_prune(sockets: &mut SocketSet<'_>, now_ticks: u64) {
-    unsafe {
-        let mut i = 0usize;
-        while i < HTTP_CONN_POOL.len() {
-            let stale = now_ticks.saturating_sub(HTTP_CONN_POOL[i].last_used_ticks) > HTTP_CONN_POOL_IDLE_TICKS;
-            let mut drop_entry = stale;
-            if !drop_entry {
-                let socket = sockets.get_mut::<tcp::Socket>(HTTP_CONN_POOL[i].handle);
-                drop_entry = !socket.is_open() || !socket.is_active();
-            }
-            if drop_entry {
-                let removed = HTTP_CONN_POOL.remove(i);
-                sockets.remove(removed.handle);
-            } else {
-                i += 1;
-            }
-        }
-    }
-}
-
-fn http_pool_take_reusable_socket(
-    sockets: &mut SocketSet<'_>,
-    host: &str,
-    port: u16,
-    is_https: bool,
-    use_https_proxy: bool,
-    now_ticks: u64,
-) -> Option<smoltcp::iface::SocketHandle> {
-    if is_https && !use_https_proxy {
-        return None;
-    }
-
-    http_pool_prune(sockets, now_ticks);
-    unsafe {
-        let mut selected = None;
-        for (idx, entry) in HTTP_CONN_POOL.iter().enumerate() {
-            if entry.port == port
-                && entry.is_https == is_https
-                && entry.use_https_proxy == use_https_proxy
-                && entry.host.eq_ignore_ascii_case(host)
-            {
-                selected = Some(idx);
-                break;
-            }
-        }
-        let idx = selected?;
-        let entry = HTTP_CONN_POOL.remove(idx);
-        let reusable = {
-            let socket = sockets.get_mut::<tcp::Socket>(entry.handle);
-            socket.is_open() && socket.is_active() && socket.may_send()
-        };
-        if reusable {
-            Some(entry.handle)
-        } else {
-            sockets.remove(entry.handle);
-            None
-        }
-    }
+fn http_cookie_prune_expired(now_ticks: u64) {
+    HTTP_COOKIE_JAR
+        .lock()
+        .retain(|cookie| !cookie.expires_at_ticks.map(|t| now_ticks >= t).unwrap_or(false));
 }
 
-fn http_pool_store_socket(
-    sockets: &mut SocketSet<'_>,
-    handle: smoltcp::iface::SocketHandle,
-    host: &str,
-    port: u16,
-    is_https: bool,
-    use_https_proxy: bool,
-    now_ticks: u64,
-) {
-    if is_https && !use_https_proxy {
-        sockets.remove(handle);
-        return;
-    }
+fn http_cookie_store(cookie: HttpCookieEntry) {
+    let mut jar = HTTP_COOKIE_JAR.lock();
+    let replace_idx = jar.iter().position(|existing| {
+        existing.name == cookie.name && existing.domain == cookie.domain && existing.path == cookie.path
+    });
 
-    let is_reusable = {
-        let socket = sockets.get_mut::<tcp::Socket>(handle);
-        socket.is_open() && socket.is_active() && socket.may_send()
-    };
-    if !is_reusable {
-        sockets.remove(handle);
+    if let Some(idx) = replace_idx {
+        jar[idx] = cookie;
         return;
     }
 
-    http_pool_prune(sockets, now_ticks);
-    unsafe {
-        let mut i = 0usize;
-        while i < HTTP_CONN_POOL.len() {
-            let same_endpoint = HTTP_CONN_POOL[i].port == port
-                && HTTP_CONN_POOL[i].is_https == is_https
-                && HTTP_CONN_POOL[i].use_https_proxy == use_https_proxy
-                && HTTP_CONN_POOL[i].host.eq_ignore_ascii_case(host);
-            if same_endpoint {
-                let removed = HTTP_CONN_POOL.remove(i);
-                if removed.handle != handle {
-                    sockets.remove(removed.handle);
-                }
-            } else {
-                i += 1;
-            }
-        }
-
-        if HTTP_CONN_POOL.len() >= HTTP_CONN_POOL_MAX_ENTRIES {
-            let removed = HTTP_CONN_POOL.remove(0);
-            if removed.handle != handle {
-                sockets.remove(removed.handle);
-            }
-        }
-
-        HTTP_CONN_POOL.push(HttpConnPoolEntry {
-            handle,
-            host: String::from(host),
-            port,
-            is_https,
-            use_https_proxy,
-            last_used_ticks: now_ticks,
-        });
-    }
-}
-
-fn ascii_lowercase(text: &str) -> String {
-    let mut out = String::with_capacity(text.len());
-    for b in text.bytes() {
-        out.push((b.to_ascii_lowercase()) as char);
+    if jar.len() >= HTTP_COOKIE_MAX_ENTRIES {
+        jar.remove(0);
     }
-    out
+    jar.push(cookie);
 }
 
-fn find_http_header_end(raw: &[u8]) -> Option<(usize, usize)> {
-    let mut i = 0usize;
-    while i + 3 < raw.len() {
-        if raw[i] == b'\r' && raw[i + 1] == b'\n' && raw[i + 2] == b'\r' && raw[i + 3] == b'\n' {
-            return Some((i, i + 4));
-        }
-        i += 1;
-    }
-    let mut j = 0usize;
-    while j + 1 < raw.len() {
-        if raw[j] == b'\n' && raw[j + 1] == b'\n' {
-            return Some((j, j + 2));
+fn http_collect_cookie_header(host: &str, path: &str, is_https: bool, now_ticks: u64) -> Option<String> {
+    http_cookie_prune_expired(now_ticks);
+    let mut parts: Vec<String> = Vec::new();
+    for cookie in HTTP_COOKIE_JAR.lock().iter() {
+        if cookie.secure && !is_https {
+            continue;
         }
-        j += 1;
-    }
-    None
-}
-
-fn parse_http_headers(raw: &[u8]) -> ParsedHttpHeaders {
-    let mut parsed = ParsedHttpHeaders::default();
-    let Some((head_end, body_offset)) = find_http_header_end(raw) else {
-        return parsed;
-    };
-    parsed.body_offset = body_offset;
-
-    let head_bytes = &raw[..head_end];
-    let head = String::from_utf8_lossy(head_bytes).into_owned();
-    let mut lines = head.lines();
-    if let Some(status_line) = lines.next() {
-        let status_trimmed = status_line.trim();
-        parsed.status_line = Some(String::from(status_trimmed));
-        let mut parts = status_trimmed.split_whitespace();
-        let _proto = parts.next();
-        parsed.status_code = parts.next().and_then(|s| s.parse::<u16>().ok());
-    }
-    for line in lines {
-        let l = line.trim();
-        if l.is_empty() {
+        if !http_cookie_domain_matches(host, cookie.domain.as_str(), cookie.host_only) {
             continue;
         }
-        if let Some((k, v)) = l.split_once(':') {
-            parsed
-                .headers
-                .push((ascii_lowercase(k.trim()), String::from(v.trim())));
+        if !http_cookie_path_matches(path, cookie.path.as_str()) {
+            continue;
         }
+        parts.push(format!("{}={}", cookie.name, cookie.value));
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("; "))
     }
-    parsed
 }
 
-fn header_first<'a>(headers: &'a [(String, String)], key: &str) -> Option<&'a str> {
-    let key_lower = ascii_lowercase(key);
-    for (k, v) in headers.iter() {
-        if *k == key_lower {
-            return Some(v.as_str());
+fn http_ingest_set_cookie_headers(
+    headers: &[(String, String)],
+    request_host: &str,
+    request_path: &str,
+    is_https: bool,
+    now_ticks: u64,
+) {
+    let set_cookie_values = header_values(headers, "set-cookie");
+    for value in set_cookie_values.into_iter() {
+        if let Some(cookie) = http_parse_set_cookie(value, request_host, request_path, is_https, now_ticks) {
+            http_cookie_store(cookie);
         }
     }
-    None
 }
 
-fn header_values<'a>(headers: &'a [(String, String)], key: &str) -> Vec<&'a str> {
-    let key_lower = ascii_lowercase(key);
-    let mut out = Vec::new();
-    for (k, v) in headers.iter() {
-        if *k == key_lower {
-            out.push(v.as_str());
-        }
-    }
-    out
+fn http_cache_lookup_index(cache: &[HttpCacheEntry], url: &str) -> Option<usize> {
+    cache.iter().position(|entry| entry.url == url)
 }
 
-fn http_cookie_domain_matches(host: &str, cookie_domain: &str, host_only: bool) -> bool {
-    let host_l = ascii_lowercase(host);
-    let dom_l = ascii_lowercase(cookie_domain);
-    if host_only {
-        return host_l == dom_l;
-    }
-    if host_l == dom_l {
-        return true;
+/// Cookies for any request; cache validators only when `cacheable` (GET).
+fn http_cache_request_hints(
+    url: &str,
+    host: &str,
+    path: &str,
+    is_https: bool,
+    cacheable: bool,
+    now_ticks: u64,
+) -> HttpRequestHints {
+    let mut hints = HttpRequestHints::default();
+    hints.cookie_header = http_collect_cookie_header(host, path, is_https, now_ticks);
+    let validators = if cacheable {
+        let cache = HTTP_CACHE.lock();
+        http_cache_lookup_index(cache.as_slice(), url)
+            .map(|idx| (cache[idx].etag.clone(), cache[idx].last_modified.clone()))
+    } else {
+        None
+    };
+    if let Some((etag, last_modified)) = validators {
+        hints.if_none_match = etag;
+        hints.if_modified_since = last_modified;
+        println("Net: HTTP cache conditional revalidate enabled.");
     }
-    host_l.ends_with(format!(".{}", dom_l).as_str())
+    hints
 }
 
-fn http_cookie_path_matches(request_path: &str, cookie_path: &str) -> bool {
-    if cookie_path.is_empty() || cookie_path == "/" {
-        return true;
-    }
-    if !request_path.starts_with(cookie_path) {
-        return false;
-    }
-    if request_path.len() == cookie_path.len() {
-        return true;
-    }
-    cookie_path.ends_with('/') || request_path.as_bytes()[cookie_path.len()] == b'/'
+fn http_cache_get_response(url: &str) -> Option<Vec<u8>> {
+    let cache = HTTP_CACHE.lock();
+    let idx = http_cache_lookup_index(cache.as_slice(), url)?;
+    Some(cache[idx].response_bytes.clone())
 }
 
-fn http_cookie_default_path(request_path: &str) -> String {
-    if !request_path.starts_with('/') {
-        return String::from("/");
+fn http_cache_store_response(url: &str, parsed: &ParsedHttpHeaders, response: &[u8], now_ticks: u64) {
+    if response.len() > HTTP_CACHE_MAX_RESPONSE_BYTES {
+        return;
     }
-    if request_path == "/" {
-        return String::from("/");
+    let cache_control = header_first(parsed.headers.as_slice(), "cache-control")
+        .map(|v| ascii_lowercase(v))
+        .unwrap_or_else(String::new);
+    if cache_control.contains("no-store") {
+        return;
     }
-    let bytes = request_path.as_bytes();
-    let mut i = bytes.len();
-    while i > 0 {
-        i -= 1;
-        if bytes[i] == b'/' {
-            if i == 0 {
-                return String::from("/");
+
+    let entry = HttpCacheEntry {
+        url: String::from(url),
+        etag: header_first(parsed.headers.as_slice(), "etag").map(String::from),
+        last_modified: header_first(parsed.headers.as_slice(), "last-modified").map(String::from),
+        response_bytes: response.to_vec(),
+        stored_at_ticks: now_ticks,
+    };
+
+    let message = {
+        let mut cache = HTTP_CACHE.lock();
+        match http_cache_lookup_index(cache.as_slice(), url) {
+            Some(idx) => {
+                cache[idx] = entry;
+                "Net: HTTP cache updated."
+            }
+            None => {
+                if cache.len() >= HTTP_CACHE_MAX_ENTRIES {
+                    cache.remove(0);
+                }
+                cache.push(entry);
+                "Net: HTTP cache stored."
             }
-            return String::from(&request_path[..i]);
         }
-    }
-    String::from("/")
+    };
+    println(message);
 }
 
-fn http_parse_set_cookie(
-    value: &str,
-    request_host: &str,
-    request_path: &str,
-    is_https: bool,
-    now_ticks: u64,
-) -> Option<HttpCookieEntry> {
-    let mut parts = value.split(';');
-    let first = parts.next()?.trim();
-    let (name_raw, value_raw) = first.split_once('=')?;
-    let name = name_raw.trim();
-    if name.is_empty() {
-        return None;
-    }
-
-    let mut cookie = HttpCookieEntry {
-        name: String::from(name),
-        value: String::from(value_raw.trim()),
-        domain: ascii_lowercase(request_host),
-        path: http_cookie_default_path(request_path),
-        secure: false,
-        host_only: true,
-        expires_at_ticks: None,
-    };
-
-    for attr in parts {
-        let token = attr.trim();
-        if token.is_empty() {
-            continue;
-        }
-        if token.eq_ignore_ascii_case("secure") {
-            cookie.secure = true;
-            continue;
-        }
-        if token.eq_ignore_ascii_case("httponly") {
-            continue;
-        }
-        if let Some((k, v)) = token.split_once('=') {
-            let key = ascii_lowercase(k.trim());
-            let val = v.trim();
-            match key.as_str() {
-                "domain" => {
-                    let normalized = val.trim_start_matches('.');
-                    if normalized.is_empty() {
-                        return None;
-                    }
-                    if !http_cookie_domain_matches(request_host, normalized, false) {
-                        return None;
-                    }
-                    cookie.domain = ascii_lowercase(normalized);
-                    cookie.host_only = false;
-                }
-                "path" => {
-                    if !val.is_empty() && val.starts_with('/') {
-                        cookie.path = String::from(val);
-                    }
-                }
-                "max-age" => {
-                    if let Ok(seconds) = val.parse::<i64>() {
-                        if seconds <= 0 {
-                            cookie.expires_at_ticks = Some(now_ticks);
-                        } else {
-                            let ttl = (seconds as u64).saturating_mul(100);
-                            cookie.expires_at_ticks = Some(now_ticks.saturating_add(ttl));
-                        }
-                    }
-                }
-                _ => {}
-            }
-        }
-    }
-
-    if cookie.secure && !is_https {
-        return None;
-    }
-    Some(cookie)
-}
-
-fn http_cookie_prune_expired(now_ticks: u64) {
-    unsafe {
-        let mut i = 0usize;
-        while i < HTTP_COOKIE_JAR.len() {
-            let expired = HTTP_COOKIE_JAR[i]
-                .expires_at_ticks
-                .map(|t| now_ticks >= t)
-                .unwrap_or(false);
-            if expired {
-                HTTP_COOKIE_JAR.remove(i);
-            } else {
-                i += 1;
-            }
-        }
-    }
-}
-
-fn http_cookie_store(cookie: HttpCookieEntry) {
-    unsafe {
-        let mut replace_idx = None;
-        for (idx, existing) in HTTP_COOKIE_JAR.iter().enumerate() {
-            if existing.name == cookie.name
-                && existing.domain == cookie.domain
-                && existing.path == cookie.path
-            {
-                replace_idx = Some(idx);
-                break;
-            }
-        }
-
-        if let Some(idx) = replace_idx {
-            HTTP_COOKIE_JAR[idx] = cookie;
-            return;
-        }
-
-        if HTTP_COOKIE_JAR.len() >= HTTP_COOKIE_MAX_ENTRIES {
-            HTTP_COOKIE_JAR.remove(0);
-        }
-        HTTP_COOKIE_JAR.push(cookie);
-    }
-}
-
-fn http_collect_cookie_header(host: &str, path: &str, is_https: bool, now_ticks: u64) -> Option<String> {
-    http_cookie_prune_expired(now_ticks);
-    let mut parts: Vec<String> = Vec::new();
-    unsafe {
-        for cookie in HTTP_COOKIE_JAR.iter() {
-            if cookie.secure && !is_https {
-                continue;
-            }
-            if !http_cookie_domain_matches(host, cookie.domain.as_str(), cookie.host_only) {
-                continue;
-            }
-            if !http_cookie_path_matches(path, cookie.path.as_str()) {
-                continue;
-            }
-            parts.push(format!("{}={}", cookie.name, cookie.value));
-        }
-    }
-    if parts.is_empty() {
-        None
-    } else {
-        Some(parts.join("; "))
-    }
-}
-
-fn http_ingest_set_cookie_headers(
-    headers: &[(String, String)],
-    request_host: &str,
-    request_path: &str,
-    is_https: bool,
-    now_ticks: u64,
-) {
-    let set_cookie_values = header_values(headers, "set-cookie");
-    for value in set_cookie_values.into_iter() {
-        if let Some(cookie) = http_parse_set_cookie(value, request_host, request_path, is_https, now_ticks) {
-            http_cookie_store(cookie);
-        }
-    }
-}
-
-fn http_cache_lookup_index(url: &str) -> Option<usize> {
-    unsafe {
-        for (idx, entry) in HTTP_CACHE.iter().enumerate() {
-            if entry.url == url {
-                return Some(idx);
-            }
-        }
-    }
-    None
-}
-
-fn http_cache_request_hints(url: &str, host: &str, path: &str, is_https: bool, now_ticks: u64) -> HttpRequestHints {
-    let mut hints = HttpRequestHints::default();
-    hints.cookie_header = http_collect_cookie_header(host, path, is_https, now_ticks);
-    if let Some(idx) = http_cache_lookup_index(url) {
-        unsafe {
-            let entry = &HTTP_CACHE[idx];
-            hints.if_none_match = entry.etag.clone();
-            hints.if_modified_since = entry.last_modified.clone();
-        }
-        println("Net: HTTP cache conditional revalidate enabled.");
-    }
-    hints
-}
-
-fn http_cache_get_response(url: &str) -> Option<Vec<u8>> {
-    let idx = http_cache_lookup_index(url)?;
-    unsafe { Some(HTTP_CACHE[idx].response_bytes.clone()) }
-}
-
-fn http_cache_store_response(url: &str, parsed: &ParsedHttpHeaders, response: &[u8], now_ticks: u64) {
-    if response.len() > HTTP_CACHE_MAX_RESPONSE_BYTES {
-        return;
-    }
-    let cache_control = header_first(parsed.headers.as_slice(), "cache-control")
-        .map(|v| ascii_lowercase(v))
-        .unwrap_or_else(String::new);
-    if cache_control.contains("no-store") {
-        return;
-    }
-
-    let entry = HttpCacheEntry {
-        url: String::from(url),
-        etag: header_first(parsed.headers.as_slice(), "etag").map(String::from),
-        last_modified: header_first(parsed.headers.as_slice(), "last-modified").map(String::from),
-        response_bytes: response.to_vec(),
-        stored_at_ticks: now_ticks,
-    };
-
-    unsafe {
-        if let Some(idx) = http_cache_lookup_index(url) {
-            HTTP_CACHE[idx] = entry;
-            println("Net: HTTP cache updated.");
-            return;
-        }
-        if HTTP_CACHE.len() >= HTTP_CACHE_MAX_ENTRIES {
-            HTTP_CACHE.remove(0);
-        }
-        HTTP_CACHE.push(entry);
-        println("Net: HTTP cache stored.");
-    }
-}
-
-fn http_decode_chunked_body(body: &[u8]) -> Option<Vec<u8>> {
-    let mut out = Vec::new();
-    let mut i = 0usize;
-    while i < body.len() {
-        let line_end_rel = body[i..]
-            .windows(2)
-            .position(|w| w == b"\r\n")
-            .or_else(|| body[i..].iter().position(|b| *b == b'\n'))?;
-        let line_end = if body.get(i + line_end_rel + 1) == Some(&b'\n')
-            && body.get(i + line_end_rel) == Some(&b'\r')
-        {
-            i + line_end_rel
-        } else {
-            i + line_end_rel
-        };
-        let line_bytes = if body.get(line_end) == Some(&b'\r') {
-            &body[i..line_end]
-        } else {
-            &body[i..=line_end]
-        };
-        let line_text = str::from_utf8(line_bytes).ok()?.trim();
-        let size_hex = line_text.split(';').next().unwrap_or("").trim();
-        let chunk_size = usize::from_str_radix(size_hex, 16).ok()?;
-
-        let after_line = if body.get(line_end) == Some(&b'\r') {
-            line_end + 2
-        } else {
-            line_end + 1
-        };
-
-        if chunk_size == 0 {
-            return Some(out);
-        }
-        let chunk_end = after_line.saturating_add(chunk_size);
-        if chunk_end > body.len() {
-            return None;
-        }
-        out.extend_from_slice(&body[after_line..chunk_end]);
-        i = chunk_end;
-        if i + 1 < body.len() && body[i] == b'\r' && body[i + 1] == b'\n' {
-            i += 2;
-        } else if i < body.len() && body[i] == b'\n' {
-            i += 1;
-        }
-    }
-    Some(out)
-}
-
-fn http_decode_gzip_body(body: &[u8]) -> Option<Vec<u8>> {
-    if body.len() < 18 {
+fn http_decode_gzip_body(body: &[u8]) -> Option<Vec<u8>> {
+    if body.len() < 18 {
         return None;
     }
     if body[0] != 0x1F || body[1] != 0x8B || body[2] != 0x08 {
@@ -1340,6 +1101,7 @@ fn http_postprocess_response(
     request_host: &str,
     request_path: &str,
     is_https: bool,
+    cacheable: bool,
     mut response: Vec<u8>,
     now_ticks: u64,
 ) -> Vec<u8> {
@@ -1356,7 +1118,7 @@ fn http_postprocess_response(
         now_ticks,
     );
 
-    if parsed.status_code == Some(304) {
+    if parsed.status_code == Some(304) && cacheable {
         if let Some(cached) = http_cache_get_response(effective_url) {
             println("Net: HTTP cache hit (304 -> cached response).");
             return cached;
@@ -1373,47 +1135,127 @@ fn http_postprocess_response(
     }
 
     let parsed_after = parse_http_headers(response.as_slice());
-    if parsed_after.status_code == Some(200) {
+    if parsed_after.status_code == Some(200) && cacheable {
         http_cache_store_response(effective_url, &parsed_after, response.as_slice(), now_ticks);
     }
 
     response
 }
 
+/// Incremental HTTP/1.1 response parser: tells when the message is complete
+/// and whether the connection may be reused afterwards. Interim 1xx
+/// responses are dropped; a HEAD response has no body whatever its
+/// `Content-Length` says.
+struct Http1Reader {
+    response: Vec<u8>,
+    head_request: bool,
+    header_parsed: bool,
+    body_offset: usize,
+    status_no_body: bool,
+    chunked: bool,
+    content_length: Option<usize>,
+    keepalive_allowed: bool,
+}
+
+impl Http1Reader {
+    fn new(head_request: bool, initial: Vec<u8>) -> Self {
+        let mut reader = Self {
+            response: initial,
+            head_request,
+            header_parsed: false,
+            body_offset: 0,
+            status_no_body: false,
+            chunked: false,
+            content_length: None,
+            keepalive_allowed: false,
+        };
+        reader.parse_head();
+        reader
+    }
+
+    fn push(&mut self, data: &[u8]) {
+        self.response.extend_from_slice(data);
+        self.parse_head();
+    }
+
+    fn parse_head(&mut self) {
+        if self.header_parsed {
+            return;
+        }
+        self.response.drain(..interim_responses_len(self.response.as_slice()));
+        let parsed = parse_http_headers(self.response.as_slice());
+        if parsed.body_offset != 0 && parsed.status_line.is_some() {
+            self.header_parsed = true;
+            self.body_offset = parsed.body_offset;
+            self.status_no_body = self.head_request || http_status_has_no_body(parsed.status_code);
+            self.chunked = header_first(parsed.headers.as_slice(), "transfer-encoding")
+                .map(|v| ascii_lowercase(v).contains("chunked"))
+                .unwrap_or(false);
+            self.content_length = header_first(parsed.headers.as_slice(), "content-length")
+                .and_then(|v| v.trim().parse::<usize>().ok());
+            let delimit_known = self.status_no_body || self.chunked || self.content_length.is_some();
+            self.keepalive_allowed = delimit_known && http_response_allows_keepalive(&parsed);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.response.is_empty()
+    }
+
+    fn is_complete(&self) -> bool {
+        if !self.header_parsed {
+            return false;
+        }
+        let body = self.response.get(self.body_offset..).unwrap_or(&[]);
+        if self.status_no_body {
+            true
+        } else if self.chunked {
+            http_decode_chunked_body(body).is_some()
+        } else if let Some(len) = self.content_length {
+            body.len() >= len
+        } else {
+            false
+        }
+    }
+
+    /// The response and whether the socket can be pooled. An incomplete
+    /// response (peer closed, timeout) is returned as-is, never pooled.
+    fn finish(self) -> Option<(Vec<u8>, bool)> {
+        if self.is_complete() {
+            Some((self.response, self.keepalive_allowed))
+        } else if self.response.is_empty() {
+            None
+        } else {
+            Some((self.response, false))
+        }
+    }
+}
+
+/// Read one HTTP/1.1 response. `initial` holds bytes already received (while
+/// waiting for `100 Continue`).
 fn http_read_http1_response(
     iface: &mut Interface,
     sockets: &mut SocketSet<'_>,
     handle: smoltcp::iface::SocketHandle,
     pump_ui: &mut impl FnMut(),
-    timeout_ticks: u64,
+    timeout_ms: u64,
+    head_request: bool,
+    initial: Vec<u8>,
 ) -> Option<(Vec<u8>, bool)> {
-    let start_read = crate::timer::ticks();
-    let mut response = Vec::new();
-
-    let mut header_parsed = false;
-    let mut body_offset = 0usize;
-    let mut status_no_body = false;
-    let mut chunked = false;
-    let mut content_length: Option<usize> = None;
-    let mut keepalive_allowed = false;
+    // Idle timeout, reset whenever bytes arrive.
+    let mut idle = crate::timer::Deadline::after_ms(timeout_ms);
+    let mut reader = Http1Reader::new(head_request, initial);
 
     loop {
         pump_ui();
-        crate::timer::on_tick();
-        let timestamp = Instant::from_millis(crate::timer::ticks() as i64 * 10);
-        let mut phy = if unsafe { crate::intel_net::GLOBAL_INTEL_NET.is_some() } {
-            ReduxPhy::Intel(crate::intel_net::IntelPhy)
-        } else {
-            ReduxPhy::Virtio(VirtioPhy)
-        };
-        iface.poll(timestamp, &mut phy, sockets);
+        net_poll_blocking(iface, sockets);
 
         let mut bytes_read = 0usize;
         let socket_is_open = {
             let socket = sockets.get_mut::<tcp::Socket>(handle);
             if socket.can_recv() {
                 if let Ok(read_len) = socket.recv(|data| {
-                    response.extend_from_slice(data);
+                    reader.push(data);
                     (data.len(), data.len())
                 }) {
                     bytes_read = read_len;
@@ -1422,50 +1264,14 @@ fn http_read_http1_response(
             socket.is_open()
         };
 
-        if !header_parsed {
-            let parsed = parse_http_headers(response.as_slice());
-            if parsed.body_offset != 0 && parsed.status_line.is_some() {
-                header_parsed = true;
-                body_offset = parsed.body_offset;
-                status_no_body = http_status_has_no_body(parsed.status_code);
-                chunked = header_first(parsed.headers.as_slice(), "transfer-encoding")
-                    .map(|v| ascii_lowercase(v).contains("chunked"))
-                    .unwrap_or(false);
-                content_length = header_first(parsed.headers.as_slice(), "content-length")
-                    .and_then(|v| v.trim().parse::<usize>().ok());
-                let delimit_known = status_no_body || chunked || content_length.is_some();
-                keepalive_allowed = delimit_known && http_response_allows_keepalive(&parsed);
-            }
-        }
-
-        if header_parsed {
-            let body = response.get(body_offset..).unwrap_or(&[]);
-            let complete = if status_no_body {
-                true
-            } else if chunked {
-                http_decode_chunked_body(body).is_some()
-            } else if let Some(len) = content_length {
-                body.len() >= len
-            } else {
-                false
-            };
-            if complete {
-                return Some((response, keepalive_allowed));
-            }
-        }
-
-        if !socket_is_open {
-            if response.is_empty() {
-                return None;
-            }
-            return Some((response, false));
+        if reader.is_complete() || !socket_is_open {
+            return reader.finish();
         }
 
-        if crate::timer::ticks().saturating_sub(start_read) > timeout_ticks {
-            if response.is_empty() {
-                return None;
-            }
-            return Some((response, false));
+        if bytes_read > 0 {
+            idle = crate::timer::Deadline::after_ms(timeout_ms);
+        } else if idle.expired() {
+            return reader.finish();
         }
 
         if bytes_read == 0 {
@@ -1700,83 +1506,46 @@ impl Http2ResponseCollector {
     }
 }
 
-const HPACK_DYNAMIC_TABLE_DEFAULT_MAX: usize = 4096;
-const HPACK_DYNAMIC_TABLE_ABSOLUTE_MAX: usize = 64 * 1024;
-
-#[derive(Clone)]
-struct HpackDynamicEntry {
-    name: String,
-    value: String,
-    size: usize,
-}
-
-#[derive(Clone)]
-struct HpackDynamicTable {
-    entries: Vec<HpackDynamicEntry>,
-    size: usize,
-    max_size: usize,
+/// A TLS connection speaking HTTP/2, kept between requests by `pool` so
+/// later requests to the same origin open new streams on it.
+pub struct Http2Session {
+    tls: tls::TlsConnection,
+    state: Http2ResponseCollector,
+    /// Frame bytes read past the end of the previous response.
+    input: Vec<u8>,
+    preface_sent: bool,
 }
 
-impl HpackDynamicTable {
-    fn new() -> Self {
+impl Http2Session {
+    fn new(tls: tls::TlsConnection) -> Self {
         Self {
-            entries: Vec::new(),
-            size: 0,
-            max_size: HPACK_DYNAMIC_TABLE_DEFAULT_MAX,
+            tls,
+            state: Http2ResponseCollector::new(),
+            input: Vec::new(),
+            preface_sent: false,
         }
     }
 
-    fn set_max_size(&mut self, max_size: usize) {
-        self.max_size = core::cmp::min(max_size, HPACK_DYNAMIC_TABLE_ABSOLUTE_MAX);
-        self.evict_to_fit();
-    }
-
-    fn evict_to_fit(&mut self) {
-        while self.size > self.max_size {
-            if let Some(last) = self.entries.pop() {
-                self.size = self.size.saturating_sub(last.size);
-            } else {
-                self.size = 0;
-                break;
-            }
-        }
+    fn streams_opened(&self) -> usize {
+        (self.state.next_local_stream_id / 2) as usize
     }
 
-    fn insert(&mut self, name: String, value: String) {
-        let entry_size = name.len().saturating_add(value.len()).saturating_add(32);
-        if entry_size > self.max_size {
-            self.entries.clear();
-            self.size = 0;
-            return;
-        }
-
-        while self.size.saturating_add(entry_size) > self.max_size {
-            if let Some(last) = self.entries.pop() {
-                self.size = self.size.saturating_sub(last.size);
-            } else {
-                self.size = 0;
-                break;
-            }
-        }
-
-        self.entries.insert(
-            0,
-            HpackDynamicEntry {
-                name,
-                value,
-                size: entry_size,
-            },
-        );
-        self.size = self.size.saturating_add(entry_size);
+    /// Whether another request can be sent on this connection.
+    fn reusable(&self) -> bool {
+        !self.state.connection_closed
+            && self.state.partial_header_stream == 0
+            && self.streams_opened() < pool::HTTP2_SESSION_MAX_STREAMS
+            && self.state.can_open_local_stream()
     }
 
-    fn get(&self, absolute_index: u32) -> Option<(&str, &str)> {
-        if absolute_index <= 61 {
-            return None;
+    /// Retarget at the next stream and drop what finished streams still
+    /// hold. Their entries stay so late frames for them are recognised.
+    fn begin_request(&mut self) {
+        self.state.target_stream_id = 0;
+        for stream in self.state.streams.values_mut().filter(|s| s.is_closed()) {
+            stream.body = Vec::new();
+            stream.headers = Vec::new();
         }
-        let dyn_index = (absolute_index as usize).checked_sub(62)?;
-        let entry = self.entries.get(dyn_index)?;
-        Some((entry.name.as_str(), entry.value.as_str()))
     }
 }
 
@@ -1959,184 +1728,29 @@ fn http2_apply_peer_settings(response: &mut Http2ResponseCollector, payload: &[u
     }
 }
 
-fn hpack_encode_prefixed_integer(out: &mut Vec<u8>, first_prefix: u8, prefix_bits: u8, value: u32) {
-    let max_prefix = (1u32 << prefix_bits) - 1;
-    if value < max_prefix {
-        out.push(first_prefix | value as u8);
-        return;
-    }
-
-    out.push(first_prefix | max_prefix as u8);
-    let mut remaining = value - max_prefix;
-    while remaining >= 128 {
-        out.push(((remaining as u8) & 0x7F) | 0x80);
-        remaining >>= 7;
-    }
-    out.push(remaining as u8);
-}
-
-fn hpack_encode_string_no_huffman(out: &mut Vec<u8>, value: &str) {
-    let bytes = value.as_bytes();
-    hpack_encode_prefixed_integer(out, 0x00, 7, bytes.len() as u32);
-    out.extend_from_slice(bytes);
-}
-
-fn hpack_encode_string_huffman(out: &mut Vec<u8>, value: &str) {
-    let mut encoded = Vec::new();
-    let mut bit_buffer: u64 = 0;
-    let mut bit_count: u32 = 0;
-
-    for &byte in value.as_bytes().iter() {
-        let (nbits, code) = HPACK_HUFFMAN_CODES[byte as usize];
-        bit_buffer = (bit_buffer << nbits) | code as u64;
-        bit_count = bit_count.saturating_add(nbits as u32);
-
-        while bit_count >= 8 {
-            let shift = bit_count - 8;
-            encoded.push(((bit_buffer >> shift) & 0xFF) as u8);
-            bit_count -= 8;
-            if bit_count == 0 {
-                bit_buffer = 0;
-            } else {
-                bit_buffer &= (1u64 << bit_count) - 1;
-            }
-        }
-    }
-
-    if bit_count > 0 {
-        let pad_bits = 8 - bit_count;
-        let padded = (bit_buffer << pad_bits) | ((1u64 << pad_bits) - 1);
-        encoded.push((padded & 0xFF) as u8);
-    }
-
-    hpack_encode_prefixed_integer(out, 0x80, 7, encoded.len() as u32);
-    out.extend_from_slice(encoded.as_slice());
-}
+fn hpack_encode_request_headers(
+    dynamic: &mut HpackDynamicTable,
+    host: &str,
+    path: &str,
+    is_https: bool,
+    use_huffman: bool,
+    hints: &HttpRequestHints,
+) -> Vec<u8> {
+    let mut out = Vec::new();
 
-fn hpack_encode_string(out: &mut Vec<u8>, value: &str, use_huffman: bool) {
-    if use_huffman {
-        hpack_encode_string_huffman(out, value);
+    hpack_encode_request_header(&mut out, dynamic, ":method", "GET", use_huffman);
+    hpack_encode_request_header(
+        &mut out,
+        dynamic,
+        ":scheme",
+        if is_https { "https" } else { "http" },
+        use_huffman,
+    );
+    hpack_encode_request_header(&mut out, dynamic, ":authority", host, use_huffman);
+    if path == "/" {
+        hpack_encode_request_header(&mut out, dynamic, ":path", "/", use_huffman);
     } else {
-        hpack_encode_string_no_huffman(out, value);
-    }
-}
-
-fn hpack_encode_indexed_header(out: &mut Vec<u8>, index: u32) {
-    hpack_encode_prefixed_integer(out, 0x80, 7, index);
-}
-
-fn hpack_find_header_exact_index(
-    dynamic: &HpackDynamicTable,
-    name: &str,
-    value: &str,
-) -> Option<u32> {
-    for idx in 1..=61u32 {
-        if let Some((n, v)) = hpack_static_table(idx) {
-            if n == name && v == value {
-                return Some(idx);
-            }
-        }
-    }
-    for (i, entry) in dynamic.entries.iter().enumerate() {
-        if entry.name == name && entry.value == value {
-            return Some(62u32.saturating_add(i as u32));
-        }
-    }
-    None
-}
-
-fn hpack_find_header_name_index(dynamic: &HpackDynamicTable, name: &str) -> Option<u32> {
-    for idx in 1..=61u32 {
-        if let Some((n, _)) = hpack_static_table(idx) {
-            if n == name {
-                return Some(idx);
-            }
-        }
-    }
-    for (i, entry) in dynamic.entries.iter().enumerate() {
-        if entry.name == name {
-            return Some(62u32.saturating_add(i as u32));
-        }
-    }
-    None
-}
-
-fn hpack_encode_literal_header(
-    out: &mut Vec<u8>,
-    dynamic: &mut HpackDynamicTable,
-    name: &str,
-    value: &str,
-    incremental_indexing: bool,
-    use_huffman: bool,
-) {
-    let name_index = hpack_find_header_name_index(dynamic, name).unwrap_or(0);
-    if incremental_indexing {
-        hpack_encode_prefixed_integer(out, 0x40, 6, name_index);
-        if name_index == 0 {
-            hpack_encode_string(out, name, use_huffman);
-        }
-        hpack_encode_string(out, value, use_huffman);
-        dynamic.insert(String::from(name), String::from(value));
-    } else {
-        hpack_encode_prefixed_integer(out, 0x00, 4, name_index);
-        if name_index == 0 {
-            hpack_encode_string(out, name, use_huffman);
-        }
-        hpack_encode_string(out, value, use_huffman);
-    }
-}
-
-fn hpack_should_index_request_header(name: &str) -> bool {
-    matches!(
-        name,
-        ":authority" | "accept" | "accept-language" | "accept-encoding" | "user-agent"
-    )
-}
-
-fn hpack_encode_request_header(
-    out: &mut Vec<u8>,
-    dynamic: &mut HpackDynamicTable,
-    name: &str,
-    value: &str,
-    use_huffman: bool,
-) {
-    if let Some(index) = hpack_find_header_exact_index(dynamic, name, value) {
-        hpack_encode_indexed_header(out, index);
-        return;
-    }
-    hpack_encode_literal_header(
-        out,
-        dynamic,
-        name,
-        value,
-        hpack_should_index_request_header(name),
-        use_huffman,
-    );
-}
-
-fn hpack_encode_request_headers(
-    dynamic: &mut HpackDynamicTable,
-    host: &str,
-    path: &str,
-    is_https: bool,
-    use_huffman: bool,
-    hints: &HttpRequestHints,
-) -> Vec<u8> {
-    let mut out = Vec::new();
-
-    hpack_encode_request_header(&mut out, dynamic, ":method", "GET", use_huffman);
-    hpack_encode_request_header(
-        &mut out,
-        dynamic,
-        ":scheme",
-        if is_https { "https" } else { "http" },
-        use_huffman,
-    );
-    hpack_encode_request_header(&mut out, dynamic, ":authority", host, use_huffman);
-    if path == "/" {
-        hpack_encode_request_header(&mut out, dynamic, ":path", "/", use_huffman);
-    } else {
-        hpack_encode_request_header(&mut out, dynamic, ":path", path, use_huffman);
+        hpack_encode_request_header(&mut out, dynamic, ":path", path, use_huffman);
     }
     hpack_encode_request_header(&mut out, dynamic, "accept", "*/*", use_huffman);
     hpack_encode_request_header(
@@ -2169,554 +1783,11 @@ fn hpack_encode_request_headers(
             use_huffman,
         );
     }
-    out
-}
-
-const HPACK_HUFFMAN_CODES: [(u8, u32); 257] = [
-    (13, 0x1ff8),
-    (23, 0x7fffd8),
-    (28, 0xfffffe2),
-    (28, 0xfffffe3),
-    (28, 0xfffffe4),
-    (28, 0xfffffe5),
-    (28, 0xfffffe6),
-    (28, 0xfffffe7),
-    (28, 0xfffffe8),
-    (24, 0xffffea),
-    (30, 0x3ffffffc),
-    (28, 0xfffffe9),
-    (28, 0xfffffea),
-    (30, 0x3ffffffd),
-    (28, 0xfffffeb),
-    (28, 0xfffffec),
-    (28, 0xfffffed),
-    (28, 0xfffffee),
-    (28, 0xfffffef),
-    (28, 0xffffff0),
-    (28, 0xffffff1),
-    (28, 0xffffff2),
-    (30, 0x3ffffffe),
-    (28, 0xffffff3),
-    (28, 0xffffff4),
-    (28, 0xffffff5),
-    (28, 0xffffff6),
-    (28, 0xffffff7),
-    (28, 0xffffff8),
-    (28, 0xffffff9),
-    (28, 0xffffffa),
-    (28, 0xffffffb),
-    (6, 0x14),
-    (10, 0x3f8),
-    (10, 0x3f9),
-    (12, 0xffa),
-    (13, 0x1ff9),
-    (6, 0x15),
-    (8, 0xf8),
-    (11, 0x7fa),
-    (10, 0x3fa),
-    (10, 0x3fb),
-    (8, 0xf9),
-    (11, 0x7fb),
-    (8, 0xfa),
-    (6, 0x16),
-    (6, 0x17),
-    (6, 0x18),
-    (5, 0x0),
-    (5, 0x1),
-    (5, 0x2),
-    (6, 0x19),
-    (6, 0x1a),
-    (6, 0x1b),
-    (6, 0x1c),
-    (6, 0x1d),
-    (6, 0x1e),
-    (6, 0x1f),
-    (7, 0x5c),
-    (8, 0xfb),
-    (15, 0x7ffc),
-    (6, 0x20),
-    (12, 0xffb),
-    (10, 0x3fc),
-    (13, 0x1ffa),
-    (6, 0x21),
-    (7, 0x5d),
-    (7, 0x5e),
-    (7, 0x5f),
-    (7, 0x60),
-    (7, 0x61),
-    (7, 0x62),
-    (7, 0x63),
-    (7, 0x64),
-    (7, 0x65),
-    (7, 0x66),
-    (7, 0x67),
-    (7, 0x68),
-    (7, 0x69),
-    (7, 0x6a),
-    (7, 0x6b),
-    (7, 0x6c),
-    (7, 0x6d),
-    (7, 0x6e),
-    (7, 0x6f),
-    (7, 0x70),
-    (7, 0x71),
-    (7, 0x72),
-    (8, 0xfc),
-    (7, 0x73),
-    (8, 0xfd),
-    (13, 0x1ffb),
-    (19, 0x7fff0),
-    (13, 0x1ffc),
-    (14, 0x3ffc),
-    (6, 0x22),
-    (15, 0x7ffd),
-    (5, 0x3),
-    (6, 0x23),
-    (5, 0x4),
-    (6, 0x24),
-    (5, 0x5),
-    (6, 0x25),
-    (6, 0x26),
-    (6, 0x27),
-    (5, 0x6),
-    (7, 0x74),
-    (7, 0x75),
-    (6, 0x28),
-    (6, 0x29),
-    (6, 0x2a),
-    (5, 0x7),
-    (6, 0x2b),
-    (7, 0x76),
-    (6, 0x2c),
-    (5, 0x8),
-    (5, 0x9),
-    (6, 0x2d),
-    (7, 0x77),
-    (7, 0x78),
-    (7, 0x79),
-    (7, 0x7a),
-    (7, 0x7b),
-    (15, 0x7ffe),
-    (11, 0x7fc),
-    (14, 0x3ffd),
-    (13, 0x1ffd),
-    (28, 0xffffffc),
-    (20, 0xfffe6),
-    (22, 0x3fffd2),
-    (20, 0xfffe7),
-    (20, 0xfffe8),
-    (22, 0x3fffd3),
-    (22, 0x3fffd4),
-    (22, 0x3fffd5),
-    (23, 0x7fffd9),
-    (22, 0x3fffd6),
-    (23, 0x7fffda),
-    (23, 0x7fffdb),
-    (23, 0x7fffdc),
-    (23, 0x7fffdd),
-    (23, 0x7fffde),
-    (24, 0xffffeb),
-    (23, 0x7fffdf),
-    (24, 0xffffec),
-    (24, 0xffffed),
-    (22, 0x3fffd7),
-    (23, 0x7fffe0),
-    (24, 0xffffee),
-    (23, 0x7fffe1),
-    (23, 0x7fffe2),
-    (23, 0x7fffe3),
-    (23, 0x7fffe4),
-    (21, 0x1fffdc),
-    (22, 0x3fffd8),
-    (23, 0x7fffe5),
-    (22, 0x3fffd9),
-    (23, 0x7fffe6),
-    (23, 0x7fffe7),
-    (24, 0xffffef),
-    (22, 0x3fffda),
-    (21, 0x1fffdd),
-    (20, 0xfffe9),
-    (22, 0x3fffdb),
-    (22, 0x3fffdc),
-    (23, 0x7fffe8),
-    (23, 0x7fffe9),
-    (21, 0x1fffde),
-    (23, 0x7fffea),
-    (22, 0x3fffdd),
-    (22, 0x3fffde),
-    (24, 0xfffff0),
-    (21, 0x1fffdf),
-    (22, 0x3fffdf),
-    (23, 0x7fffeb),
-    (23, 0x7fffec),
-    (21, 0x1fffe0),
-    (21, 0x1fffe1),
-    (22, 0x3fffe0),
-    (21, 0x1fffe2),
-    (23, 0x7fffed),
-    (22, 0x3fffe1),
-    (23, 0x7fffee),
-    (23, 0x7fffef),
-    (20, 0xfffea),
-    (22, 0x3fffe2),
-    (22, 0x3fffe3),
-    (22, 0x3fffe4),
-    (23, 0x7ffff0),
-    (22, 0x3fffe5),
-    (22, 0x3fffe6),
-    (23, 0x7ffff1),
-    (26, 0x3ffffe0),
-    (26, 0x3ffffe1),
-    (20, 0xfffeb),
-    (19, 0x7fff1),
-    (22, 0x3fffe7),
-    (23, 0x7ffff2),
-    (22, 0x3fffe8),
-    (25, 0x1ffffec),
-    (26, 0x3ffffe2),
-    (26, 0x3ffffe3),
-    (26, 0x3ffffe4),
-    (27, 0x7ffffde),
-    (27, 0x7ffffdf),
-    (26, 0x3ffffe5),
-    (24, 0xfffff1),
-    (25, 0x1ffffed),
-    (19, 0x7fff2),
-    (21, 0x1fffe3),
-    (26, 0x3ffffe6),
-    (27, 0x7ffffe0),
-    (27, 0x7ffffe1),
-    (26, 0x3ffffe7),
-    (27, 0x7ffffe2),
-    (24, 0xfffff2),
-    (21, 0x1fffe4),
-    (21, 0x1fffe5),
-    (26, 0x3ffffe8),
-    (26, 0x3ffffe9),
-    (28, 0xffffffd),
-    (27, 0x7ffffe3),
-    (27, 0x7ffffe4),
-    (27, 0x7ffffe5),
-    (20, 0xfffec),
-    (24, 0xfffff3),
-    (20, 0xfffed),
-    (21, 0x1fffe6),
-    (22, 0x3fffe9),
-    (21, 0x1fffe7),
-    (21, 0x1fffe8),
-    (23, 0x7ffff3),
-    (22, 0x3fffea),
-    (22, 0x3fffeb),
-    (25, 0x1ffffee),
-    (25, 0x1ffffef),
-    (24, 0xfffff4),
-    (24, 0xfffff5),
-    (26, 0x3ffffea),
-    (23, 0x7ffff4),
-    (26, 0x3ffffeb),
-    (27, 0x7ffffe6),
-    (26, 0x3ffffec),
-    (26, 0x3ffffed),
-    (27, 0x7ffffe7),
-    (27, 0x7ffffe8),
-    (27, 0x7ffffe9),
-    (27, 0x7ffffea),
-    (27, 0x7ffffeb),
-    (28, 0xffffffe),
-    (27, 0x7ffffec),
-    (27, 0x7ffffed),
-    (27, 0x7ffffee),
-    (27, 0x7ffffef),
-    (27, 0x7fffff0),
-    (26, 0x3ffffee),
-    (30, 0x3fffffff),
-];
-
-fn hpack_huffman_lookup_symbol(prefix: u32, bit_len: u8) -> Option<u16> {
-    let mut sym = 0usize;
-    while sym < HPACK_HUFFMAN_CODES.len() {
-        let (len, code) = HPACK_HUFFMAN_CODES[sym];
-        if len == bit_len && code == prefix {
-            return Some(sym as u16);
-        }
-        sym += 1;
-    }
-    None
-}
-
-fn hpack_decode_huffman(raw: &[u8]) -> Option<Vec<u8>> {
-    let mut out = Vec::with_capacity(raw.len());
-    let mut acc: u64 = 0;
-    let mut bit_len: u8 = 0;
-
-    for byte in raw.iter().copied() {
-        if bit_len > 56 {
-            return None;
-        }
-        acc = (acc << 8) | byte as u64;
-        bit_len = bit_len.saturating_add(8);
-
-        loop {
-            let mut matched = false;
-            let mut l = 5u8;
-            while l <= 30 {
-                if bit_len < l {
-                    l += 1;
-                    continue;
-                }
-
-                let mask = (1u64 << l) - 1;
-                let prefix = ((acc >> (bit_len - l)) & mask) as u32;
-                if let Some(sym) = hpack_huffman_lookup_symbol(prefix, l) {
-                    if sym == 256 {
-                        return None;
-                    }
-                    out.push(sym as u8);
-                    bit_len -= l;
-                    if bit_len == 0 {
-                        acc = 0;
-                    } else {
-                        let keep_mask = (1u64 << bit_len) - 1;
-                        acc &= keep_mask;
-                    }
-                    matched = true;
-                    break;
-                }
-                l += 1;
-            }
-
-            if !matched {
-                break;
-            }
-        }
-    }
-
-    // RFC7541: remaining bits must be <= 7 and all ones (EOS padding).
-    if bit_len > 7 {
-        return None;
-    }
-    if bit_len > 0 {
-        let pad_mask = (1u64 << bit_len) - 1;
-        if (acc & pad_mask) != pad_mask {
-            return None;
-        }
-    }
-
-    Some(out)
-}
-
-fn hpack_decode_prefixed_integer(buf: &[u8], idx: &mut usize, prefix_bits: u8) -> Option<u32> {
-    let first = *buf.get(*idx)?;
-    let mask = (1u8 << prefix_bits) - 1;
-    let mut value = (first & mask) as u32;
-    *idx += 1;
-    if value < mask as u32 {
-        return Some(value);
-    }
-
-    let mut shift = 0u32;
-    loop {
-        let b = *buf.get(*idx)?;
-        *idx += 1;
-        value = value.checked_add(((b & 0x7F) as u32) << shift)?;
-        if (b & 0x80) == 0 {
-            break;
-        }
-        shift = shift.saturating_add(7);
-        if shift > 28 {
-            return None;
-        }
-    }
-    Some(value)
-}
-
-fn hpack_decode_string(buf: &[u8], idx: &mut usize) -> Option<String> {
-    let first = *buf.get(*idx)?;
-    let huffman = (first & 0x80) != 0;
-    let len = hpack_decode_prefixed_integer(buf, idx, 7)? as usize;
-    let end = idx.checked_add(len)?;
-    if end > buf.len() {
-        return None;
-    }
-    let raw = &buf[*idx..end];
-    *idx = end;
-
-    if huffman {
-        let decoded = hpack_decode_huffman(raw)?;
-        return Some(String::from_utf8_lossy(decoded.as_slice()).into_owned());
-    }
-
-    Some(String::from_utf8_lossy(raw).into_owned())
-}
-
-fn hpack_static_table(index: u32) -> Option<(&'static str, &'static str)> {
-    // RFC 7541 Appendix A (1..=61)
-    match index {
-        1 => Some((":authority", "")),
-        2 => Some((":method", "GET")),
-        3 => Some((":method", "POST")),
-        4 => Some((":path", "/")),
-        5 => Some((":path", "/index.html")),
-        6 => Some((":scheme", "http")),
-        7 => Some((":scheme", "https")),
-        8 => Some((":status", "200")),
-        9 => Some((":status", "204")),
-        10 => Some((":status", "206")),
-        11 => Some((":status", "304")),
-        12 => Some((":status", "400")),
-        13 => Some((":status", "404")),
-        14 => Some((":status", "500")),
-        15 => Some(("accept-charset", "")),
-        16 => Some(("accept-encoding", "gzip, deflate")),
-        17 => Some(("accept-language", "")),
-        18 => Some(("accept-ranges", "")),
-        19 => Some(("accept", "")),
-        20 => Some(("access-control-allow-origin", "")),
-        21 => Some(("age", "")),
-        22 => Some(("allow", "")),
-        23 => Some(("authorization", "")),
-        24 => Some(("cache-control", "")),
-        25 => Some(("content-disposition", "")),
-        26 => Some(("content-encoding", "")),
-        27 => Some(("content-language", "")),
-        28 => Some(("content-length", "")),
-        29 => Some(("content-location", "")),
-        30 => Some(("content-range", "")),
-        31 => Some(("content-type", "")),
-        32 => Some(("cookie", "")),
-        33 => Some(("date", "")),
-        34 => Some(("etag", "")),
-        35 => Some(("expect", "")),
-        36 => Some(("expires", "")),
-        37 => Some(("from", "")),
-        38 => Some(("host", "")),
-        39 => Some(("if-match", "")),
-        40 => Some(("if-modified-since", "")),
-        41 => Some(("if-none-match", "")),
-        42 => Some(("if-range", "")),
-        43 => Some(("if-unmodified-since", "")),
-        44 => Some(("last-modified", "")),
-        45 => Some(("link", "")),
-        46 => Some(("location", "")),
-        47 => Some(("max-forwards", "")),
-        48 => Some(("proxy-authenticate", "")),
-        49 => Some(("proxy-authorization", "")),
-        50 => Some(("range", "")),
-        51 => Some(("referer", "")),
-        52 => Some(("refresh", "")),
-        53 => Some(("retry-after", "")),
-        54 => Some(("server", "")),
-        55 => Some(("set-cookie", "")),
-        56 => Some(("strict-transport-security", "")),
-        57 => Some(("transfer-encoding", "")),
-        58 => Some(("user-agent", "")),
-        59 => Some(("vary", "")),
-        60 => Some(("via", "")),
-        61 => Some(("www-authenticate", "")),
-        _ => None,
-    }
-}
-
-fn hpack_lookup_header(index: u32, dynamic: &HpackDynamicTable) -> Option<(String, String)> {
-    if index == 0 {
-        return None;
-    }
-    if let Some((n, v)) = hpack_static_table(index) {
-        return Some((String::from(n), String::from(v)));
-    }
-    dynamic
-        .get(index)
-        .map(|(n, v)| (String::from(n), String::from(v)))
-}
-
-fn hpack_lookup_name(index: u32, dynamic: &HpackDynamicTable) -> Option<String> {
-    if index == 0 {
-        return None;
-    }
-    if let Some((n, _)) = hpack_static_table(index) {
-        return Some(String::from(n));
-    }
-    dynamic.get(index).map(|(n, _)| String::from(n))
-}
-
-fn hpack_decode_header_block(
-    block: &[u8],
-    dynamic: &mut HpackDynamicTable,
-    out_headers: &mut Vec<(String, String)>,
-    out_status: &mut Option<u16>,
-) {
-    let mut idx = 0usize;
-    while idx < block.len() {
-        let byte = block[idx];
-
-        // Dynamic table size update: 001xxxxx
-        if (byte & 0xE0) == 0x20 {
-            let Some(new_size) = hpack_decode_prefixed_integer(block, &mut idx, 5) else {
-                break;
-            };
-            dynamic.set_max_size(new_size as usize);
-            continue;
-        }
-
-        // Indexed header field: 1xxxxxxx
-        if (byte & 0x80) != 0 {
-            let Some(index) = hpack_decode_prefixed_integer(block, &mut idx, 7) else {
-                break;
-            };
-            if let Some((name, value)) = hpack_lookup_header(index, dynamic) {
-                if name == ":status" {
-                    if let Ok(code) = value.parse::<u16>() {
-                        *out_status = Some(code);
-                    }
-                } else if !name.starts_with(':') {
-                    out_headers.push((name, value));
-                }
-            }
-            continue;
-        }
-
-        // Literal header field:
-        // 01xxxxxx (incremental indexing) or 0000xxxx (without indexing) or 0001xxxx (never indexed)
-        let (incremental, prefix) = if (byte & 0xC0) == 0x40 {
-            (true, 6u8)
-        } else {
-            (false, 4u8)
-        };
-        let Some(name_index) = hpack_decode_prefixed_integer(block, &mut idx, prefix) else {
-            break;
-        };
-
-        let name = if name_index == 0 {
-            match hpack_decode_string(block, &mut idx) {
-                Some(text) => text,
-                None => continue,
-            }
-        } else {
-            match hpack_lookup_name(name_index, dynamic) {
-                Some(n) => n,
-                None => continue,
-            }
-        };
-
-        let value = match hpack_decode_string(block, &mut idx) {
-            Some(text) => text,
-            None => continue,
-        };
-
-        if name == ":status" {
-            if let Ok(code) = value.parse::<u16>() {
-                *out_status = Some(code);
-            }
-        } else {
-            if !name.starts_with(':') {
-                out_headers.push((name.clone(), value.clone()));
-            }
-        }
-
-        if incremental {
-            dynamic.insert(name, value);
-        }
+    for (name, value) in hints.extra_headers.iter() {
+        let name_lower = ascii_lowercase(name.as_str());
+        hpack_encode_request_header(&mut out, dynamic, name_lower.as_str(), value.as_str(), use_huffman);
     }
+    out
 }
 
 fn http2_drain_frames(
@@ -2872,12 +1943,15 @@ fn http2_drain_frames(
                         if let Some(header_block) = complete_header_block {
                             let mut parsed_headers = Vec::new();
                             let mut parsed_status = None;
-                            hpack_decode_header_block(
+                            if !hpack_decode_header_block(
                                 header_block.as_slice(),
                                 &mut response.dynamic_table,
                                 &mut parsed_headers,
                                 &mut parsed_status,
-                            );
+                            ) {
+                                // COMPRESSION_ERROR: our table no longer matches the peer's.
+                                response.connection_closed = true;
+                            }
                             if let Some(stream) = response.stream_mut(stream_id) {
                                 if let Some(code) = parsed_status {
                                     stream.status = Some(code);
@@ -2911,12 +1985,14 @@ fn http2_drain_frames(
                     if let Some(header_block) = complete_header_block {
                         let mut parsed_headers = Vec::new();
                         let mut parsed_status = None;
-                        hpack_decode_header_block(
+                        if !hpack_decode_header_block(
                             header_block.as_slice(),
                             &mut response.dynamic_table,
                             &mut parsed_headers,
                             &mut parsed_status,
-                        );
+                        ) {
+                            response.connection_closed = true;
+                        }
                         if let Some(stream) = response.stream_mut(stream_id) {
                             if let Some(code) = parsed_status {
                                 stream.status = Some(code);
@@ -3115,23 +2191,27 @@ fn http2_build_synthesized_http_response_bytes(response: &Http2ResponseCollector
     out
 }
 
+/// Open one GET stream per path. The connection preface goes out with the
+/// first request of a session only.
 fn http2_send_get_requests(
-    tls: &mut crate::net::tls::TlsConnection,
+    session: &mut Http2Session,
     socket: &mut tcp::Socket,
-    response: &mut Http2ResponseCollector,
     host: &str,
     paths: &[&str],
     is_https: bool,
     request_hints: &HttpRequestHints,
 ) -> bool {
     let mut request = Vec::new();
-    request.extend_from_slice(HTTP2_CLIENT_PREFACE);
+    if !session.preface_sent {
+        request.extend_from_slice(HTTP2_CLIENT_PREFACE);
 
-    // SETTINGS: disable server push (id=0x2, value=0)
-    let settings_payload = [0x00u8, 0x02, 0x00, 0x00, 0x00, 0x00];
-    if !http2_build_frame(HTTP2_FRAME_SETTINGS, 0, 0, &settings_payload, &mut request) {
-        return false;
+        // SETTINGS: disable server push (id=0x2, value=0)
+        let settings_payload = [0x00u8, 0x02, 0x00, 0x00, 0x00, 0x00];
+        if !http2_build_frame(HTTP2_FRAME_SETTINGS, 0, 0, &settings_payload, &mut request) {
+            return false;
+        }
     }
+    let response = &mut session.state;
 
     let mut opened = 0usize;
     for path in paths.iter().copied() {
@@ -3166,289 +2246,793 @@ fn http2_send_get_requests(
     if opened == 0 {
         return false;
     }
-    tls.write(socket, request.as_slice()) == request.len()
+    let sent = session.tls.write(socket, request.as_slice()) == request.len();
+    session.preface_sent |= sent;
+    sent
 }
 
-fn http_get_request_bytes_with_timeout_once(
-    url: &str,
+/// Resolve `host` (dotted IPv4 or a DNS name) while keeping the UI pumped.
+fn resolve_ipv4_blocking(
+    iface: &mut Interface,
+    sockets: &mut SocketSet<'static>,
+    host: &str,
     pump_ui: &mut impl FnMut(),
     timeout_ticks: u64,
-) -> Option<Vec<u8>> {
-    // Very simple HTTP 1.0 Client (Blocking)
-    // URL ignored for now, always connects to 1.1.1.1 (Cloudflare) or similar
-    // to prove connectivity.
-    
-    unsafe {
-        if IFACE.is_none() || SOCKETS.is_none() {
-            println("Net: Stack not initialized.");
-            return None;
-        }
-        
-        let iface = IFACE.as_mut().unwrap();
-        let sockets = SOCKETS.as_mut().unwrap();
-        
-        let is_https = starts_with_ignore_ascii_case(url, "https://");
-        let use_https_proxy = is_https && is_https_proxy_enabled() && !is_https_proxy_url(url);
-        let effective_url_storage = if use_https_proxy {
-            build_https_proxy_url(url)
-        } else {
-            String::from(url)
-        };
-        let effective_url = effective_url_storage.as_str();
-
-        let Some((host, port, path)) = parse_url(effective_url) else {
-            println("Net: Invalid URL format. Use http://domain.com/ or http://1.2.3.4/");
-            return None;
-        };
-        
-        // If using native HTTPS, default port 443 if not specified.
-        let port = if port == 80 && is_https && !use_https_proxy { 443 } else { port };
-        let request_hints = http_cache_request_hints(
-            effective_url,
-            host.as_str(),
-            path,
-            is_https && !use_https_proxy,
-            crate::timer::ticks(),
-        );
-
-        let mut reused_pooled_socket = false;
-        let handle = if let Some(existing) = http_pool_take_reusable_socket(
-            sockets,
-            host.as_str(),
-            port,
-            is_https,
-            use_https_proxy,
-            crate::timer::ticks(),
-        ) {
-            reused_pooled_socket = true;
-            println("Net: HTTP keep-alive socket reused.");
-            existing
-        } else {
-            // Resolve Host
-            let remote_addr = if let Ok(ip) = host.parse::<Ipv4Address>() {
-                ip
-            } else {
-                // Use DNS
-                let dns_handle = DNS_HANDLE.expect("DNS not initialized");
-                println(&alloc::format!("Net: Resolving {}...", host));
-                
-                let query_handle = {
-                    let dns_socket = sockets.get_mut::<dns::Socket>(dns_handle);
-                    dns_socket.start_query(iface.context(), &host, smoltcp::wire::DnsQueryType::A).ok()?
-                };
-
-                let start_dns = crate::timer::ticks();
-                let mut resolved_ip = None;
-                while crate::timer::ticks() - start_dns < timeout_ticks {
-                    pump_ui();
-                    crate::timer::on_tick();
-                    let timestamp = Instant::from_millis(crate::timer::ticks() as i64 * 10);
-                    let mut phy = if crate::intel_net::GLOBAL_INTEL_NET.is_some() {
-                        ReduxPhy::Intel(crate::intel_net::IntelPhy)
-                    } else {
-                        ReduxPhy::Virtio(VirtioPhy)
-                    };
-                    iface.poll(timestamp, &mut phy, sockets);
-
-                    let dns_socket = sockets.get_mut::<dns::Socket>(dns_handle);
-                    match dns_socket.get_query_result(query_handle) {
-                        Ok(addrs) => {
-                            for addr in addrs {
-                                if let smoltcp::wire::IpAddress::Ipv4(ip) = addr {
-                                    resolved_ip = Some(ip);
-                                    break;
-                                }
-                            }
-                            if resolved_ip.is_some() { break; }
-                        },
-                        Err(dns::GetQueryResultError::Pending) => {
-                            pump_ui();
-                            uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
-                            continue;
-                        }
-                        Err(_) => {
-                            println("Net: DNS Resolution Failed");
-                            return None;
-                        }
-                    }
+) -> Option<Ipv4Address> {
+    if let Ok(ip) = host.parse::<Ipv4Address>() {
+        return Some(ip);
+    }
 
-                    pump_ui();
-                    uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
-                }
-                
-                resolved_ip?
-            };
+    let _t = crate::trace::scope_with("net", "dns", host);
+    let dns_handle = unsafe { *DNS_HANDLE.get() }.expect("DNS not initialized");
+    let host = dhcp::qualify_host(host);
+    let host = host.as_str();
+    println(&alloc::format!("Net: Resolving {}...", host));
 
-            let rx_buffer = alloc::vec![0u8; 4096];
-            let tx_buffer = alloc::vec![0u8; 4096];
-            // Must leak to get 'static lifetime for now
-            let rx_static = alloc::boxed::Box::leak(rx_buffer.into_boxed_slice());
-            let tx_static = alloc::boxed::Box::leak(tx_buffer.into_boxed_slice());
+    let query_handle = {
+        let dns_socket = sockets.get_mut::<dns::Socket>(dns_handle);
+        dns_socket.start_query(iface.context(), host, smoltcp::wire::DnsQueryType::A).ok()?
+    };
 
-            let socket = tcp::Socket::new(
-                tcp::SocketBuffer::new(&mut rx_static[..]),
-                tcp::SocketBuffer::new(&mut tx_static[..]),
-            );
-            let handle = sockets.add(socket);
-            let socket = sockets.get_mut::<tcp::Socket>(handle);
-            
-            crate::println(&alloc::format!("Net: Connecting to {}:{}...", remote_addr, port));
-            
-            if let Err(_e) = socket.connect(iface.context(), (remote_addr, port), 49152 + (crate::timer::ticks() % 10000) as u16) {
-                println("Net: Connect failed");
-                sockets.remove(handle);
-                return None;
-            }
-            
-            // Blocking loop to connect
-            let start = crate::timer::ticks();
-            loop {
-                pump_ui();
-                crate::timer::on_tick();
-                let timestamp = Instant::from_millis(crate::timer::ticks() as i64 * 10);
-                let mut phy = if crate::intel_net::GLOBAL_INTEL_NET.is_some() {
-                    ReduxPhy::Intel(crate::intel_net::IntelPhy)
-                } else {
-                    ReduxPhy::Virtio(VirtioPhy)
-                };
-                iface.poll(timestamp, &mut phy, sockets);
-                
-                let (may_send, is_active) = {
-                    let socket = sockets.get_mut::<tcp::Socket>(handle);
-                    (socket.may_send(), socket.is_active())
-                };
-                if may_send {
-                    break;
-                }
-                if !is_active {
-                    println("Net: Connect failed");
-                    sockets.remove(handle);
-                    return None;
-                }
-                
-                if crate::timer::ticks() - start > timeout_ticks {
-                    println("Net: Connect Timeout");
-                    sockets.remove(handle);
-                    return None;
+    let start_dns = crate::timer::ticks();
+    while crate::timer::ticks() - start_dns < timeout_ticks {
+        pump_ui();
+        net_poll_blocking(iface, sockets);
+
+        let dns_socket = sockets.get_mut::<dns::Socket>(dns_handle);
+        match dns_socket.get_query_result(query_handle) {
+            Ok(addrs) => {
+                for addr in addrs {
+                    if let smoltcp::wire::IpAddress::Ipv4(ip) = addr {
+                        return Some(ip);
+                    }
                 }
-    
-                pump_ui();
-                uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
-            }
-            handle
-        };
-    
-            let mut response: Vec<u8> = Vec::new();
-            let mut keepalive_reusable = false;
-            let host_header = if (is_https && port != 443) || (!is_https && port != 80) {
-                alloc::format!("{}:{}", host, port)
-            } else {
-                host.clone()
-            };
-            let connection_header = if is_https && !use_https_proxy {
-                "close"
-            } else {
-                "keep-alive"
-            };
-            // Browser-like request headers improve compatibility with modern sites/CDN/WAFs.
-            let mut req = alloc::format!(
-                "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36 GoOS/0.2\r\nAccept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8\r\nAccept-Language: en-US,en;q=0.9,es;q=0.8\r\nAccept-Encoding: {}\r\nCache-Control: no-cache\r\nPragma: no-cache\r\nConnection: {}\r\n",
-                path,
-                host_header,
-                HTTP_ACCEPT_ENCODING_VALUE,
-                connection_header,
-            );
-            if connection_header == "keep-alive" {
-                req.push_str("Keep-Alive: timeout=20, max=8\r\n");
             }
-            if let Some(cookie) = request_hints.cookie_header.as_ref() {
-                req.push_str("Cookie: ");
-                req.push_str(cookie.as_str());
-                req.push_str("\r\n");
-            }
-            if let Some(etag) = request_hints.if_none_match.as_ref() {
-                req.push_str("If-None-Match: ");
-                req.push_str(etag.as_str());
-                req.push_str("\r\n");
-            }
-            if let Some(modified) = request_hints.if_modified_since.as_ref() {
-                req.push_str("If-Modified-Since: ");
-                req.push_str(modified.as_str());
-                req.push_str("\r\n");
+            Err(dns::GetQueryResultError::Pending) => {}
+            Err(_) => {
+                println("Net: DNS Resolution Failed");
+                return None;
             }
-            req.push_str("\r\n");
-    
-             if is_https && !use_https_proxy {
-                 crate::println("Net: Initializing TLS...");
-                 crate::println(
-                     alloc::format!("Net: TLS root CA store -> {}", webpki_roots::TLS_SERVER_ROOTS.len()).as_str()
-                 );
-                 let mut tls = match crate::net::tls::TlsConnection::new(&host) {
-                     Some(t) => t,
-                     None => {
-                         sockets.remove(handle);
-                         return None;
-                     }
-                 };
-    
-                 // Handshake Loop
-                 let start_handshake = crate::timer::ticks();
-                 loop {
-                     pump_ui();
-                     crate::timer::on_tick();
-                     let timestamp = Instant::from_millis(crate::timer::ticks() as i64 * 10);
-                     let mut phy = if crate::intel_net::GLOBAL_INTEL_NET.is_some() {
-                         ReduxPhy::Intel(crate::intel_net::IntelPhy)
-                     } else {
-                         ReduxPhy::Virtio(VirtioPhy)
-                     };
-                     iface.poll(timestamp, &mut phy, sockets);
-                     
-                     let socket = sockets.get_mut::<tcp::Socket>(handle);
-                     if !socket.is_active() { break; }
-                     
-                     match tls.process_handshake(socket) {
-                         crate::net::tls::HandshakeStatus::Done => break,
-                         crate::net::tls::HandshakeStatus::Error => {
-                             println("Net: TLS Handshake Failed.");
-                             sockets.remove(handle);
-                             return None;
-                         }
-                         crate::net::tls::HandshakeStatus::InProgress => {}
-                     }
-                     
-                     if crate::timer::ticks() - start_handshake > timeout_ticks {
-                         println("Net: TLS Handshake Timeout");
-                         sockets.remove(handle);
-                         return None;
-                     }
-                     pump_ui();
-                     uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
-                 }
-                 
-                 crate::println("Net: TLS Handshake Success!");
-                 crate::println(
-                     alloc::format!("Net: TLS ALPN -> {}", tls.selected_alpn_label()).as_str()
-                 );
-
-                 let alpn_is_h2 = tls
-                     .selected_alpn()
-                     .map(|p| p == b"h2")
-                     .unwrap_or(false);
+        }
 
-                 if alpn_is_h2 {
-                     crate::println("Net: HTTP/2 enabled (ALPN h2).");
-                     let mut stream_response = Http2ResponseCollector::new();
+        pump_ui();
+        uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
+    }
+    None
+}
 
-                     {
+/// Advance the timer and run one `iface.poll` on the active NIC.
+fn net_poll_blocking(iface: &mut Interface, sockets: &mut SocketSet<'_>) {
+    crate::timer::on_tick();
+    let timestamp = Instant::from_millis(crate::timer::ticks() as i64 * 10);
+    let mut phy = if crate::intel_net::is_present() {
+        ReduxPhy::Intel(crate::intel_net::IntelPhy)
+    } else {
+        ReduxPhy::Virtio(VirtioPhy)
+    };
+    iface.poll(timestamp, &mut phy, sockets);
+}
+
+/// Clamp a configured socket buffer size in KiB to bytes.
+fn tcp_buffer_bytes(kib: i64) -> usize {
+    (kib.clamp(TCP_BUFFER_MIN_KIB, TCP_BUFFER_MAX_KIB) as usize) * 1024
+}
+
+/// (rx, tx) buffer sizes for new TCP sockets, from `net.tcp_rx_kib` and
+/// `net.tcp_tx_kib`. The receive side sets the advertised window, so it is
+/// what bounds download throughput; smoltcp turns on window scaling when it
+/// is over 64 KiB.
+pub fn tcp_buffer_sizes() -> (usize, usize) {
+    (
+        tcp_buffer_bytes(crate::config::get_int(TCP_RX_KIB_KEY, TCP_RX_DEFAULT_KIB)),
+        tcp_buffer_bytes(crate::config::get_int(TCP_TX_KIB_KEY, TCP_TX_DEFAULT_KIB)),
+    )
+}
+
+/// Per-socket TCP options, applied when a socket is connected or taken from
+/// the keep-alive pool.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TcpOptions {
+    /// Probe the peer after this much idle time so NAT mappings stay open;
+    /// `None` sends no keep-alives.
+    pub keep_alive_ms: Option<u64>,
+    /// Turn Nagle off so small writes go out at once.
+    pub nodelay: bool,
+    /// Abort when the peer acknowledges nothing for this long; `None` leaves
+    /// it to the caller's wait timeout.
+    pub user_timeout_ms: Option<u64>,
+}
+
+impl TcpOptions {
+    /// Options from `net.tcp_keepalive_s` (0 = off) and `net.tcp_nodelay`.
+    pub fn from_settings(keep_alive_s: i64, nodelay: bool) -> Self {
+        Self {
+            keep_alive_ms: (keep_alive_s > 0).then_some(keep_alive_s as u64 * 1000),
+            nodelay,
+            user_timeout_ms: None,
+        }
+    }
+
+    pub fn defaults() -> Self {
+        Self::from_settings(
+            crate::config::get_int(TCP_KEEPALIVE_KEY, TCP_KEEPALIVE_DEFAULT_S),
+            crate::config::get_bool(TCP_NODELAY_KEY, false),
+        )
+    }
+
+    fn apply(&self, socket: &mut tcp::Socket) {
+        socket.set_keep_alive(self.keep_alive_ms.map(smoltcp::time::Duration::from_millis));
+        socket.set_nagle_enabled(!self.nodelay);
+        socket.set_timeout(self.user_timeout_ms.map(smoltcp::time::Duration::from_millis));
+    }
+}
+
+/// Milliseconds to timer ticks, at least one.
+pub fn ms_to_ticks(ms: u64) -> u64 {
+    (ms / MS_PER_TICK).max(1)
+}
+
+/// Ephemeral port for a new connection. The counter keeps sockets opened
+/// within the same tick (background requests) from colliding.
+fn tcp_local_port() -> u16 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    // Random start, then a fixed stride so consecutive ports never collide.
+    static OFFSET: AtomicU64 = AtomicU64::new(u64::MAX);
+    if OFFSET.load(Ordering::Relaxed) == u64::MAX {
+        OFFSET.store(crate::random::next_u64() % 16_000, Ordering::Relaxed);
+    }
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    49152 + ((OFFSET.load(Ordering::Relaxed).wrapping_add(n.wrapping_mul(7919))) % 16_000) as u16
+}
+
+/// Add a TCP socket and start connecting it to `remote:port`; the
+/// handshake completes in later polls.
+fn tcp_open_socket(
+    iface: &mut Interface,
+    sockets: &mut SocketSet<'static>,
+    remote_addr: Ipv4Address,
+    port: u16,
+    options: &TcpOptions,
+) -> Option<smoltcp::iface::SocketHandle> {
+    let (rx_bytes, tx_bytes) = tcp_buffer_sizes();
+    // Owned buffers: they are freed with the socket instead of leaking on
+    // every connect.
+    let socket = tcp::Socket::new(
+        tcp::SocketBuffer::new(alloc::vec![0u8; rx_bytes]),
+        tcp::SocketBuffer::new(alloc::vec![0u8; tx_bytes]),
+    );
+    let handle = sockets.add(socket);
+    let socket = sockets.get_mut::<tcp::Socket>(handle);
+    options.apply(socket);
+
+    crate::println(&alloc::format!("Net: Connecting to {}:{}...", remote_addr, port));
+
+    if let Err(_e) = socket.connect(iface.context(), (remote_addr, port), tcp_local_port()) {
+        println("Net: Connect failed");
+        sockets.remove(handle);
+        return None;
+    }
+    Some(handle)
+}
+
+/// Open a TCP socket to `remote:port` with the default options.
+fn tcp_connect_blocking(
+    iface: &mut Interface,
+    sockets: &mut SocketSet<'static>,
+    remote_addr: Ipv4Address,
+    port: u16,
+    pump_ui: &mut impl FnMut(),
+    timeout_ticks: u64,
+) -> Option<smoltcp::iface::SocketHandle> {
+    let options = TcpOptions::defaults();
+    tcp_connect_blocking_with(iface, sockets, remote_addr, port, pump_ui, timeout_ticks, &options)
+}
+
+/// Open a TCP socket to `remote:port` and wait until it can send.
+fn tcp_connect_blocking_with(
+    iface: &mut Interface,
+    sockets: &mut SocketSet<'static>,
+    remote_addr: Ipv4Address,
+    port: u16,
+    pump_ui: &mut impl FnMut(),
+    timeout_ticks: u64,
+    options: &TcpOptions,
+) -> Option<smoltcp::iface::SocketHandle> {
+    let handle = tcp_open_socket(iface, sockets, remote_addr, port, options)?;
+
+    // Blocking loop to connect
+    let start = crate::timer::ticks();
+    loop {
+        pump_ui();
+        net_poll_blocking(iface, sockets);
+
+        let (may_send, is_active) = {
+            let socket = sockets.get_mut::<tcp::Socket>(handle);
+            (socket.may_send(), socket.is_active())
+        };
+        if may_send {
+            return Some(handle);
+        }
+        if !is_active {
+            println("Net: Connect failed");
+            sockets.remove(handle);
+            return None;
+        }
+
+        if crate::timer::ticks() - start > timeout_ticks {
+            println("Net: Connect Timeout");
+            sockets.remove(handle);
+            return None;
+        }
+
+        pump_ui();
+        uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
+    }
+}
+
+/// Connect, optionally run a TLS handshake, send `request` and read until
+/// the peer closes, `max_bytes` arrive or the timeout expires. For the
+/// request/response protocols that close the connection after one reply
+/// (Gemini, Gopher).
+pub(crate) fn tcp_request_until_close(
+    host: &str,
+    port: u16,
+    tls: Option<tls::TlsConnection>,
+    request: &[u8],
+    pump_ui: &mut impl FnMut(),
+    timeout_ticks: u64,
+    max_bytes: usize,
+) -> Option<Vec<u8>> {
+    let first_line = request.split(|b| *b == b'\r' || *b == b'\n').next().unwrap_or(&[]);
+    let key = format!("TCP {}:{} {}", host, port, String::from_utf8_lossy(first_line));
+    if let Some(reply) = crate::replay::network_reply(key.as_str()) {
+        return reply;
+    }
+    let response = tcp_request_until_close_live(host, port, tls, request, pump_ui, timeout_ticks, max_bytes);
+    crate::replay::record_reply(key.as_str(), response.as_deref());
+    response
+}
+
+fn tcp_request_until_close_live(
+    host: &str,
+    port: u16,
+    mut tls: Option<tls::TlsConnection>,
+    request: &[u8],
+    pump_ui: &mut impl FnMut(),
+    timeout_ticks: u64,
+    max_bytes: usize,
+) -> Option<Vec<u8>> {
+    unsafe {
+        if IFACE.get().is_none() || SOCKETS.get().is_none() {
+            println("Net: Stack not initialized.");
+            return None;
+        }
+        let iface = IFACE.get_mut().as_mut().unwrap();
+        let sockets = SOCKETS.get_mut().as_mut().unwrap();
+
+        let remote_addr = resolve_ipv4_blocking(iface, sockets, host, pump_ui, timeout_ticks)?;
+        let handle = tcp_connect_blocking(iface, sockets, remote_addr, port, pump_ui, timeout_ticks)?;
+
+        if let Some(tls) = tls.as_mut() {
+            if !tls_handshake_blocking(iface, sockets, handle, tls, pump_ui, timeout_ticks) {
+                sockets.remove(handle);
+                return None;
+            }
+        }
+
+        let sent = {
+            let socket = sockets.get_mut::<tcp::Socket>(handle);
+            match tls.as_mut() {
+                Some(tls) => tls.write(socket, request) == request.len(),
+                None => socket.can_send() && socket.send_slice(request).map(|n| n == request.len()).unwrap_or(false),
+            }
+        };
+        if !sent {
+            println("Net: request send failed.");
+            sockets.remove(handle);
+            return None;
+        }
+
+        let response = tcp_read_until_close(iface, sockets, handle, tls.as_mut(), pump_ui, timeout_ticks, max_bytes);
+        sockets.remove(handle);
+
+        if response.is_empty() {
+            None
+        } else {
+            Some(response)
+        }
+    }
+}
+
+/// Drive the TLS handshake on a connected socket. On failure the socket is
+/// left in the set for the caller to remove.
+fn tls_handshake_blocking(
+    iface: &mut Interface,
+    sockets: &mut SocketSet<'static>,
+    handle: smoltcp::iface::SocketHandle,
+    tls: &mut tls::TlsConnection,
+    pump_ui: &mut impl FnMut(),
+    timeout_ticks: u64,
+) -> bool {
+    let start_handshake = crate::timer::ticks();
+    loop {
+        pump_ui();
+        net_poll_blocking(iface, sockets);
+        let socket = sockets.get_mut::<tcp::Socket>(handle);
+        if !socket.is_active() {
+            return false;
+        }
+        match tls.process_handshake(socket) {
+            tls::HandshakeStatus::Done => return true,
+            tls::HandshakeStatus::Error => {
+                println("Net: TLS Handshake Failed.");
+                return false;
+            }
+            tls::HandshakeStatus::InProgress => {}
+        }
+        if crate::timer::ticks() - start_handshake > timeout_ticks {
+            println("Net: TLS Handshake Timeout");
+            return false;
+        }
+        uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
+    }
+}
+
+/// Write all of `data` to a connected socket, a chunk at a time, for the
+/// protocols that keep the connection open between commands (FTP, IMAP,
+/// SMTP).
+/// Queue the next chunk of `data` if the send buffer has room for it;
+/// returns how many bytes were taken (0 when the buffer is full).
+fn tcp_send_some(socket: &mut tcp::Socket, tls: Option<&mut tls::TlsConnection>, data: &[u8]) -> usize {
+    let chunk = &data[..TCP_SEND_CHUNK.min(data.len())];
+    // TlsConnection::write either queues the whole record or nothing, so
+    // only call it when the record is sure to fit.
+    let needed = chunk.len() + if tls.is_some() { TLS_RECORD_OVERHEAD } else { 0 };
+    if socket.send_capacity() - socket.send_queue() < needed {
+        return 0;
+    }
+    match tls {
+        Some(tls) => tls.write(socket, chunk),
+        None => socket.send_slice(chunk).unwrap_or(0),
+    }
+}
+
+fn tcp_send_all_blocking(
+    iface: &mut Interface,
+    sockets: &mut SocketSet<'static>,
+    handle: smoltcp::iface::SocketHandle,
+    mut tls: Option<&mut tls::TlsConnection>,
+    data: &[u8],
+    pump_ui: &mut impl FnMut(),
+    timeout_ticks: u64,
+) -> Result<(), &'static str> {
+    let mut sent = 0usize;
+    let mut last_progress = crate::timer::ticks();
+    while sent < data.len() {
+        pump_ui();
+        net_poll_blocking(iface, sockets);
+        let socket = sockets.get_mut::<tcp::Socket>(handle);
+        if !socket.may_send() {
+            return Err("conexion cerrada por el servidor");
+        }
+
+        let written = tcp_send_some(socket, tls.as_deref_mut(), &data[sent..]);
+
+        if written > 0 {
+            sent += written;
+            last_progress = crate::timer::ticks();
+            continue;
+        }
+        if crate::timer::ticks() - last_progress > timeout_ticks {
+            return Err("timeout enviando datos");
+        }
+        uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
+    }
+    Ok(())
+}
+
+/// Close `handle`, wait (bounded) for the FIN exchange and drop the socket.
+fn tcp_close_blocking(
+    iface: &mut Interface,
+    sockets: &mut SocketSet<'static>,
+    handle: smoltcp::iface::SocketHandle,
+    pump_ui: &mut impl FnMut(),
+    timeout_ticks: u64,
+) {
+    sockets.get_mut::<tcp::Socket>(handle).close();
+    let start = crate::timer::ticks();
+    while crate::timer::ticks() - start < timeout_ticks {
+        pump_ui();
+        net_poll_blocking(iface, sockets);
+        if !sockets.get_mut::<tcp::Socket>(handle).is_active() {
+            break;
+        }
+        uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
+    }
+    sockets.remove(handle);
+}
+
+/// Read from `handle` until the peer closes, `max_bytes` arrive or nothing
+/// comes in for `timeout_ticks`. The socket stays in the set.
+fn tcp_read_until_close(
+    iface: &mut Interface,
+    sockets: &mut SocketSet<'static>,
+    handle: smoltcp::iface::SocketHandle,
+    mut tls: Option<&mut tls::TlsConnection>,
+    pump_ui: &mut impl FnMut(),
+    timeout_ticks: u64,
+    max_bytes: usize,
+) -> Vec<u8> {
+    let mut response = Vec::new();
+    // TlsConnection::read drops whatever part of a record does not fit,
+    // so the buffer has to hold a full 16 KiB TLS record.
+    let mut read_buf = alloc::vec![0u8; TLS_MAX_RECORD_PLAINTEXT];
+    let mut last_data = crate::timer::ticks();
+    loop {
+        pump_ui();
+        net_poll_blocking(iface, sockets);
+        let socket = sockets.get_mut::<tcp::Socket>(handle);
+        let len = match tls.as_mut() {
+            Some(tls) => tls.read(socket, &mut read_buf),
+            None => socket.recv_slice(&mut read_buf).unwrap_or(0),
+        };
+        response.extend_from_slice(&read_buf[..len]);
+        if response.len() >= max_bytes {
+            response.truncate(max_bytes);
+            break;
+        }
+        if len > 0 {
+            last_data = crate::timer::ticks();
+        } else {
+            // Drained and the peer has sent FIN: the reply is complete.
+            if !socket.may_recv() || !socket.is_active() {
+                break;
+            }
+            if crate::timer::ticks() - last_data > timeout_ticks {
+                println("Net: read timeout.");
+                break;
+            }
+            uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
+        }
+    }
+    response
+}
+
+/// What happened to a request body.
+enum HttpBodyOutcome {
+    /// Sent (or there was none); holds response bytes that came in first.
+    Sent(Vec<u8>),
+    /// The server answered with a final status before `100 Continue`, so
+    /// the body was never sent; holds the start of that response.
+    Refused(Vec<u8>),
+    Failed,
+}
+
+/// Send `request.body` after its head went out. With `Expect: 100-continue`
+/// wait up to `EXPECT_CONTINUE_WAIT_MS` for the server's go-ahead, and
+/// send anyway if it stays silent (RFC 9110 10.1.1).
+fn http_send_request_body(
+    iface: &mut Interface,
+    sockets: &mut SocketSet<'static>,
+    handle: smoltcp::iface::SocketHandle,
+    mut tls: Option<&mut tls::TlsConnection>,
+    request: &request::HttpRequest,
+    pump_ui: &mut impl FnMut(),
+) -> HttpBodyOutcome {
+    if request.body.is_empty() {
+        return HttpBodyOutcome::Sent(Vec::new());
+    }
+    let mut early = Vec::new();
+    if request.expects_continue() {
+        let mut buf = alloc::vec![0u8; TLS_MAX_RECORD_PLAINTEXT];
+        let deadline = crate::timer::Deadline::after_ms(request::EXPECT_CONTINUE_WAIT_MS);
+        while !deadline.expired() {
+            pump_ui();
+            net_poll_blocking(iface, sockets);
+            let socket = sockets.get_mut::<tcp::Socket>(handle);
+            let len = match tls.as_mut() {
+                Some(tls) => tls.read(socket, &mut buf),
+                None => socket.recv_slice(&mut buf).unwrap_or(0),
+            };
+            early.extend_from_slice(&buf[..len]);
+            if find_http_header_end(early.as_slice()).is_some() || !socket.may_recv() {
+                break;
+            }
+            if len == 0 {
+                uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
+            }
+        }
+        let interim = interim_responses_len(early.as_slice());
+        if interim > 0 {
+            early.drain(..interim);
+        } else if find_http_header_end(early.as_slice()).is_some() {
+            println("Net: HTTP server answered before 100-continue; body not sent.");
+            return HttpBodyOutcome::Refused(early);
+        }
+    }
+    match tcp_send_all_blocking(iface, sockets, handle, tls, request.body.as_slice(), pump_ui, ms_to_ticks(request.timeout_ms)) {
+        Ok(()) => HttpBodyOutcome::Sent(early),
+        Err(e) => {
+            println(alloc::format!("Net: HTTP body send failed ({}).", e).as_str());
+            HttpBodyOutcome::Failed
+        }
+    }
+}
+
+/// Where a request goes once the HTTPS compatibility proxy is applied,
+/// with the cookie/cache headers and the caller's own headers.
+struct HttpTarget {
+    effective_url: String,
+    host: String,
+    port: u16,
+    path: String,
+    is_https: bool,
+    use_https_proxy: bool,
+    hints: HttpRequestHints,
+}
+
+fn http_target(request: &request::HttpRequest) -> Option<HttpTarget> {
+    let url = request.url.as_str();
+    let cacheable = request.method == HttpMethod::Get;
+    let is_https = starts_with_ignore_ascii_case(url, "https://");
+    // The compatibility proxy only relays GETs.
+    let use_https_proxy = is_https && cacheable && is_https_proxy_enabled() && !is_https_proxy_url(url);
+    let effective_url = if use_https_proxy {
+        build_https_proxy_url(url)
+    } else {
+        String::from(url)
+    };
+
+    let Some((host, port, path)) = parse_url(effective_url.as_str()) else {
+        println("Net: Invalid URL format. Use http://domain.com/ or http://1.2.3.4/");
+        return None;
+    };
+    let path = String::from(path);
+
+    // If using native HTTPS, default port 443 if not specified.
+    let port = if port == 80 && is_https && !use_https_proxy { 443 } else { port };
+    let mut hints = http_cache_request_hints(
+        effective_url.as_str(),
+        host.as_str(),
+        path.as_str(),
+        is_https && !use_https_proxy,
+        cacheable,
+        crate::timer::ticks(),
+    );
+    hints.extra_headers.extend_from_slice(request.headers.as_slice());
+    Some(HttpTarget {
+        effective_url,
+        host,
+        port,
+        path,
+        is_https,
+        use_https_proxy,
+        hints,
+    })
+}
+
+/// The HTTP/1.1 request line and headers for `request`, ending in the blank
+/// line. Direct TLS connections close after one response; plain ones ask
+/// for keep-alive so the socket can go back to the pool.
+fn http1_request_head(
+    request: &request::HttpRequest,
+    host: &str,
+    port: u16,
+    path: &str,
+    is_https: bool,
+    use_https_proxy: bool,
+    request_hints: &HttpRequestHints,
+) -> String {
+    let method = request.method;
+    let host_header = if (is_https && port != 443) || (!is_https && port != 80) {
+        alloc::format!("{}:{}", host, port)
+    } else {
+        String::from(host)
+    };
+    let connection_header = if is_https && !use_https_proxy {
+        "close"
+    } else {
+        "keep-alive"
+    };
+    // Browser-like request headers improve compatibility with modern sites/CDN/WAFs.
+    let mut req = alloc::format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36 GoOS/0.2\r\nAccept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8\r\nAccept-Language: en-US,en;q=0.9,es;q=0.8\r\nAccept-Encoding: {}\r\nCache-Control: no-cache\r\nPragma: no-cache\r\nConnection: {}\r\n",
+        method.as_str(),
+        path,
+        host_header,
+        HTTP_ACCEPT_ENCODING_VALUE,
+        connection_header,
+    );
+    if connection_header == "keep-alive" {
+        req.push_str("Keep-Alive: timeout=20, max=8\r\n");
+    }
+    if let Some(cookie) = request_hints.cookie_header.as_ref() {
+        req.push_str("Cookie: ");
+        req.push_str(cookie.as_str());
+        req.push_str("\r\n");
+    }
+    if let Some(etag) = request_hints.if_none_match.as_ref() {
+        req.push_str("If-None-Match: ");
+        req.push_str(etag.as_str());
+        req.push_str("\r\n");
+    }
+    if let Some(modified) = request_hints.if_modified_since.as_ref() {
+        req.push_str("If-Modified-Since: ");
+        req.push_str(modified.as_str());
+        req.push_str("\r\n");
+    }
+    for (name, value) in request_hints.extra_headers.iter() {
+        req.push_str(name.as_str());
+        req.push_str(": ");
+        req.push_str(value.as_str());
+        req.push_str("\r\n");
+    }
+    if method.has_body() || !request.body.is_empty() {
+        req.push_str(alloc::format!("Content-Length: {}\r\n", request.body.len()).as_str());
+    }
+    if request.expects_continue() {
+        req.push_str("Expect: 100-continue\r\n");
+    }
+    req.push_str("\r\n");
+    req
+}
+
+/// Run the TLS handshake on a connected socket, offering h2 through ALPN
+/// when `http2` is set. The socket is removed on failure.
+fn http_tls_handshake_blocking(
+    iface: &mut Interface,
+    sockets: &mut SocketSet<'static>,
+    handle: smoltcp::iface::SocketHandle,
+    host: &str,
+    http2: bool,
+    pump_ui: &mut impl FnMut(),
+    timeout_ms: u64,
+) -> Option<tls::TlsConnection> {
+    println("Net: Initializing TLS...");
+    println(format!("Net: TLS root CA store -> {}", webpki_roots::TLS_SERVER_ROOTS.len()).as_str());
+    let tls_conn = if http2 {
+        tls::TlsConnection::new(host)
+    } else {
+        tls::TlsConnection::new_http11(host)
+    };
+    let Some(mut tls) = tls_conn else {
+        sockets.remove(handle);
+        return None;
+    };
+
+    let deadline = crate::timer::Deadline::after_ms(timeout_ms);
+    loop {
+        pump_ui();
+        net_poll_blocking(iface, sockets);
+
+        let socket = sockets.get_mut::<tcp::Socket>(handle);
+        if !socket.is_active() {
+            break;
+        }
+
+        match tls.process_handshake(socket) {
+            tls::HandshakeStatus::Done => break,
+            tls::HandshakeStatus::Error => {
+                println("Net: TLS Handshake Failed.");
+                sockets.remove(handle);
+                return None;
+            }
+            tls::HandshakeStatus::InProgress => {}
+        }
+
+        if deadline.expired() {
+            println("Net: TLS Handshake Timeout");
+            sockets.remove(handle);
+            return None;
+        }
+        pump_ui();
+        uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
+    }
+
+    println("Net: TLS Handshake Success!");
+    println(format!("Net: TLS ALPN -> {}", tls.selected_alpn_label()).as_str());
+    Some(tls)
+}
+
+/// One attempt at `request`, without retries or redirects. `request_sent`
+/// is set once the request head has been handed to the socket, so the
+/// caller knows whether a failed non-idempotent request may be repeated.
+fn http_request_once(
+    request: &request::HttpRequest,
+    pump_ui: &mut impl FnMut(),
+    request_sent: &mut bool,
+) -> Option<Vec<u8>> {
+    let method = request.method;
+    let timeout_ms = request.timeout_ms;
+    // DNS and connect share their helpers with the tick-based clients.
+    let timeout_ticks = ms_to_ticks(timeout_ms);
+    let options = &request.options;
+    let cacheable = method == HttpMethod::Get;
+
+    unsafe {
+        if IFACE.get().is_none() || SOCKETS.get().is_none() {
+            println("Net: Stack not initialized.");
+            return None;
+        }
+        
+        let iface = IFACE.get_mut().as_mut().unwrap();
+        let sockets = SOCKETS.get_mut().as_mut().unwrap();
+
+        let HttpTarget {
+            effective_url,
+            host,
+            port,
+            path,
+            is_https,
+            use_https_proxy,
+            hints: request_hints,
+        } = http_target(request)?;
+        let effective_url = effective_url.as_str();
+        let path = path.as_str();
+
+        // A pooled socket the server already closed would swallow the
+        // request, which only idempotent methods can afford. Direct TLS
+        // origins only pool HTTP/2 sessions, which only carry GETs.
+        let origin = pool::Origin::new(host.as_str(), port, is_https, use_https_proxy);
+        let direct_tls = is_https && !use_https_proxy;
+        let checkout = if (direct_tls && cacheable) || (!direct_tls && method.is_idempotent()) {
+            pool::take(sockets, &origin, !direct_tls, crate::timer::ticks())
+        } else {
+            None
+        };
+        let mut reused_pooled_socket = false;
+        let mut served_requests = 0u32;
+        let mut pooled_h2: Option<alloc::boxed::Box<Http2Session>> = None;
+        let handle = if let Some(checkout) = checkout {
+            reused_pooled_socket = true;
+            served_requests = checkout.requests;
+            match checkout.conn {
+                pool::PooledConn::Http2(session) => {
+                    println("Net: HTTP/2 session reused.");
+                    pooled_h2 = Some(session);
+                }
+                pool::PooledConn::Http1 => println("Net: HTTP keep-alive socket reused."),
+            }
+            options.apply(sockets.get_mut::<tcp::Socket>(checkout.handle));
+            checkout.handle
+        } else {
+            let remote_addr = resolve_ipv4_blocking(iface, sockets, host.as_str(), pump_ui, timeout_ticks)?;
+            tcp_connect_blocking_with(iface, sockets, remote_addr, port, pump_ui, timeout_ticks, options)?
+        };
+    
+            let mut response: Vec<u8> = Vec::new();
+            let mut keepalive_reusable = false;
+            let mut pool_conn = pool::PooledConn::Http1;
+            let req = http1_request_head(request, &host, port, path, is_https, use_https_proxy, &request_hints);
+    
+             if is_https && !use_https_proxy {
+                 let mut tls_http11 = None;
+                 let session = match pooled_h2.take() {
+                     Some(session) => Some(session),
+                     None => {
+                         // The HTTP/2 path only sends GET.
+                         let tls = http_tls_handshake_blocking(iface, sockets, handle, &host, cacheable, pump_ui, timeout_ms)?;
+                         if tls.selected_alpn().map(|p| p == b"h2").unwrap_or(false) {
+                             crate::println("Net: HTTP/2 enabled (ALPN h2).");
+                             Some(alloc::boxed::Box::new(Http2Session::new(tls)))
+                         } else {
+                             tls_http11 = Some(tls);
+                             None
+                         }
+                     }
+                 };
+
+                 if let Some(mut session) = session {
+                     session.begin_request();
+
+                     {
                          let socket = sockets.get_mut::<tcp::Socket>(handle);
                          if !http2_send_get_requests(
-                             &mut tls,
+                             &mut session,
                              socket,
-                             &mut stream_response,
                              &host,
-                            &[path],
-                            is_https,
+                             &[path],
+                             is_https,
                              &request_hints,
                          ) {
                              println("Net: HTTP/2 request send failed.");
@@ -3456,20 +3040,14 @@ fn http_get_request_bytes_with_timeout_once(
                              return None;
                          }
                      }
+                     *request_sent = true;
 
-                     let start_read = crate::timer::ticks();
+                     // Idle timeout: a large body keeps going while data flows.
+                     let mut idle = crate::timer::Deadline::after_ms(timeout_ms);
                      let mut tls_read_buf = [0u8; 2048];
-                     let mut frame_input = Vec::new();
                      loop {
                          pump_ui();
-                         crate::timer::on_tick();
-                         let timestamp = Instant::from_millis(crate::timer::ticks() as i64 * 10);
-                         let mut phy = if crate::intel_net::GLOBAL_INTEL_NET.is_some() {
-                             ReduxPhy::Intel(crate::intel_net::IntelPhy)
-                         } else {
-                             ReduxPhy::Virtio(VirtioPhy)
-                         };
-                         iface.poll(timestamp, &mut phy, sockets);
+                         net_poll_blocking(iface, sockets);
 
                          let mut pending_tx = Vec::new();
                          {
@@ -3477,32 +3055,30 @@ fn http_get_request_bytes_with_timeout_once(
                              if !socket.is_active() {
                                  break;
                              }
-                             let read_len = tls.read(socket, &mut tls_read_buf);
+                             let read_len = session.tls.read(socket, &mut tls_read_buf);
                              if read_len > 0 {
-                                 frame_input.extend_from_slice(&tls_read_buf[..read_len]);
+                                 session.input.extend_from_slice(&tls_read_buf[..read_len]);
+                                 idle = crate::timer::Deadline::after_ms(timeout_ms);
                              }
                          }
 
-                         if !frame_input.is_empty() {
-                             http2_drain_frames(
-                                 &mut frame_input,
-                                 &mut stream_response,
-                                 &mut pending_tx,
-                             );
+                         if !session.input.is_empty() {
+                             let Http2Session { input, state, .. } = &mut *session;
+                             http2_drain_frames(input, state, &mut pending_tx);
                          }
 
                          if !pending_tx.is_empty() {
                              let socket = sockets.get_mut::<tcp::Socket>(handle);
                              for frame in pending_tx.iter() {
-                                 let _ = tls.write(socket, frame.as_slice());
+                                 let _ = session.tls.write(socket, frame.as_slice());
                              }
                          }
 
-                         if stream_response.target_stream_closed() {
+                         if session.state.target_stream_closed() {
                              break;
                          }
 
-                         if crate::timer::ticks() - start_read > timeout_ticks {
+                         if idle.expired() {
                              println("Net: HTTP/2 read timeout.");
                              break;
                          }
@@ -3510,22 +3086,48 @@ fn http_get_request_bytes_with_timeout_once(
                          uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
                      }
 
-                     if stream_response.target_body_is_empty() {
+                     if reused_pooled_socket && session.state.target_status().is_none() {
+                         // The server dropped the idle session; the retry
+                         // opens a new connection.
+                         println("Net: HTTP/2 pooled session closed by server.");
+                         sockets.remove(handle);
+                         return None;
+                     }
+                     if session.state.target_body_is_empty() {
                          println("Net: HTTP/2 returned empty body; fallback to HTTP/1.1 parser path.");
                      }
-                     response = http2_build_synthesized_http_response_bytes(&stream_response);
-                 } else {
+                     response = http2_build_synthesized_http_response_bytes(&session.state);
+                     let stream_done = session
+                         .state
+                         .streams
+                         .get(&session.state.target_stream_id)
+                         .map(|s| s.is_closed() && s.reset_error_code.is_none())
+                         .unwrap_or(false);
+                     if stream_done && session.reusable() {
+                         keepalive_reusable = true;
+                         pool_conn = pool::PooledConn::Http2(session);
+                     }
+                 } else if let Some(mut tls) = tls_http11 {
                      let socket = sockets.get_mut::<tcp::Socket>(handle);
                      tls.write(socket, req.as_bytes());
+                     *request_sent = true;
+                     match http_send_request_body(iface, sockets, handle, Some(&mut tls), request, pump_ui) {
+                         // The connection closes after this response either way.
+                         HttpBodyOutcome::Sent(early) | HttpBodyOutcome::Refused(early) => response = early,
+                         HttpBodyOutcome::Failed => {
+                             sockets.remove(handle);
+                             return None;
+                         }
+                     }
 
                      // TLS Read Loop (HTTP/1.1 over TLS)
-                     let start_read = crate::timer::ticks();
+                     let mut idle = crate::timer::Deadline::after_ms(timeout_ms);
                      let mut read_buf = [0u8; 1024];
                      loop {
                          pump_ui();
                          crate::timer::on_tick();
                          let timestamp = Instant::from_millis(crate::timer::ticks() as i64 * 10);
-                         let mut phy = if crate::intel_net::GLOBAL_INTEL_NET.is_some() {
+                         let mut phy = if crate::intel_net::is_present() {
                              ReduxPhy::Intel(crate::intel_net::IntelPhy)
                          } else {
                              ReduxPhy::Virtio(VirtioPhy)
@@ -3538,14 +3140,16 @@ fn http_get_request_bytes_with_timeout_once(
                          let len = tls.read(socket, &mut read_buf);
                          if len > 0 {
                              response.extend_from_slice(&read_buf[..len]);
+                             idle = crate::timer::Deadline::after_ms(timeout_ms);
                          }
 
-                         if crate::timer::ticks() - start_read > timeout_ticks {
+                         if idle.expired() {
                              break;
                          }
                          pump_ui();
                          uefi::boot::stall(NET_BLOCKING_LOOP_STALL_US);
                      }
+                     response.drain(..interim_responses_len(response.as_slice()));
                  }
             } else {
                  if use_https_proxy {
@@ -3554,7 +3158,7 @@ fn http_get_request_bytes_with_timeout_once(
                  if reused_pooled_socket {
                      println("Net: HTTP request using pooled keep-alive socket.");
                  }
-                 crate::println(&alloc::format!("Net: Connected! Sending GET {}...", path));
+                 crate::println(&alloc::format!("Net: Connected! Sending {} {}...", method.as_str(), path));
                  let send_ok = {
                      let socket = sockets.get_mut::<tcp::Socket>(handle);
                      socket.can_send() && socket.send_slice(req.as_bytes()).is_ok()
@@ -3564,28 +3168,43 @@ fn http_get_request_bytes_with_timeout_once(
                      sockets.remove(handle);
                      return None;
                  }
+                 *request_sent = true;
+                 let (early, body_sent) = match http_send_request_body(iface, sockets, handle, None, request, pump_ui) {
+                     HttpBodyOutcome::Sent(early) => (early, true),
+                     HttpBodyOutcome::Refused(early) => (early, false),
+                     HttpBodyOutcome::Failed => {
+                         sockets.remove(handle);
+                         return None;
+                     }
+                 };
 
-                 if let Some((plain_response, can_reuse_socket)) =
-                     http_read_http1_response(iface, sockets, handle, pump_ui, timeout_ticks)
-                 {
+                 if let Some((plain_response, can_reuse_socket)) = http_read_http1_response(
+                     iface,
+                     sockets,
+                     handle,
+                     pump_ui,
+                     timeout_ms,
+                     method == HttpMethod::Head,
+                     early,
+                 ) {
                      response = plain_response;
-                     keepalive_reusable = can_reuse_socket;
+                     // After a refused body the server may still be reading it.
+                     keepalive_reusable = can_reuse_socket && body_sent;
                  }
             }
         
         println("Net: Request complete.");
 
         if keepalive_reusable {
-            http_pool_store_socket(
+            pool::store(
                 sockets,
                 handle,
-                host.as_str(),
-                port,
-                is_https,
-                use_https_proxy,
+                origin,
+                pool_conn,
+                served_requests.saturating_add(1),
                 crate::timer::ticks(),
             );
-            println("Net: HTTP keep-alive socket returned to pool.");
+            println("Net: HTTP keep-alive connection returned to pool.");
         } else {
             sockets.remove(handle);
         }
@@ -3598,6 +3217,7 @@ fn http_get_request_bytes_with_timeout_once(
                 host.as_str(),
                 path,
                 is_https && !use_https_proxy,
+                cacheable,
                 response,
                 crate::timer::ticks(),
             ))
@@ -3608,70 +3228,21 @@ fn http_get_request_bytes_with_timeout_once(
 pub fn http_get_request_bytes_with_timeout(
     url: &str,
     pump_ui: &mut impl FnMut(),
-    timeout_ticks: u64,
+    timeout_ms: u64,
 ) -> Option<Vec<u8>> {
-    let mut attempt = 0usize;
-    while attempt < HTTP_RETRY_MAX_ATTEMPTS {
-        let response = http_get_request_bytes_with_timeout_once(url, pump_ui, timeout_ticks);
-        match response {
-            Some(bytes) => {
-                let parsed = parse_http_headers(bytes.as_slice());
-                let retryable = parsed
-                    .status_code
-                    .map(http_should_retry_status)
-                    .unwrap_or(false);
-                if retryable && attempt + 1 < HTTP_RETRY_MAX_ATTEMPTS {
-                    let backoff = http_retry_backoff_ticks(attempt);
-                    let status = parsed.status_code.unwrap_or(0);
-                    println(
-                        format!(
-                            "Net: HTTP retry {}/{} after status {} (backoff={} ticks).",
-                            attempt + 1,
-                            HTTP_RETRY_MAX_ATTEMPTS - 1,
-                            status,
-                            backoff
-                        )
-                        .as_str(),
-                    );
-                    http_wait_ticks_with_ui(pump_ui, backoff);
-                    attempt += 1;
-                    continue;
-                }
-                return Some(bytes);
-            }
-            None => {
-                if attempt + 1 < HTTP_RETRY_MAX_ATTEMPTS {
-                    let backoff = http_retry_backoff_ticks(attempt);
-                    println(
-                        format!(
-                            "Net: HTTP retry {}/{} after network failure (backoff={} ticks).",
-                            attempt + 1,
-                            HTTP_RETRY_MAX_ATTEMPTS - 1,
-                            backoff
-                        )
-                        .as_str(),
-                    );
-                    http_wait_ticks_with_ui(pump_ui, backoff);
-                    attempt += 1;
-                    continue;
-                }
-                return None;
-            }
-        }
-    }
-    None
+    request::HttpRequest::get(url).timeout_ms(timeout_ms).send(pump_ui)
 }
 
 pub fn http_get_request_bytes(url: &str, pump_ui: &mut impl FnMut()) -> Option<Vec<u8>> {
-    http_get_request_bytes_with_timeout(url, pump_ui, NET_BLOCKING_TIMEOUT_TICKS)
+    http_get_request_bytes_with_timeout(url, pump_ui, NET_BLOCKING_TIMEOUT_MS)
 }
 
 pub fn http_get_request_with_timeout(
     url: &str,
     pump_ui: &mut impl FnMut(),
-    timeout_ticks: u64,
+    timeout_ms: u64,
 ) -> Option<String> {
-    let bytes = http_get_request_bytes_with_timeout(url, pump_ui, timeout_ticks)?;
+    let bytes = http_get_request_bytes_with_timeout(url, pump_ui, timeout_ms)?;
     Some(String::from_utf8_lossy(bytes.as_slice()).into_owned())
 }
 
@@ -3682,7 +3253,7 @@ pub fn http_get_request(url: &str, pump_ui: &mut impl FnMut()) -> Option<String>
 
 pub fn get_ip_address() -> Option<IpAddress> {
     unsafe {
-        IFACE.as_ref().and_then(|iface| {
+        IFACE.get().as_ref().and_then(|iface| {
             iface
                 .ip_addrs()
                 .iter()
@@ -3693,24 +3264,22 @@ pub fn get_ip_address() -> Option<IpAddress> {
 }
 
 pub fn get_gateway() -> Option<IpAddress> {
-    unsafe { IPV4_GATEWAY }
+    LINK.lock().gateway
 }
 
 pub fn get_active_transport() -> &'static str {
-    unsafe { ACTIVE_TRANSPORT }
+    LINK.lock().active_transport
 }
 
 pub fn get_failover_policy() -> &'static str {
-    unsafe { FAILOVER_POLICY }
+    LINK.lock().failover_policy
 }
 
 pub fn get_network_mode() -> &'static str {
-    unsafe {
-        if USE_STATIC_IPV4_RUNTIME {
-            NET_MODE_STATIC
-        } else {
-            NET_MODE_DHCP
-        }
+    if use_static_ipv4() {
+        NET_MODE_STATIC
+    } else {
+        NET_MODE_DHCP
     }
 }
 
@@ -3723,53 +3292,39 @@ pub fn get_https_mode() -> &'static str {
 }
 
 pub fn is_https_proxy_enabled() -> bool {
-    unsafe { HTTPS_PROXY_ENABLED }
+    LINK.lock().https_proxy_enabled
 }
 
 pub fn get_static_ipv4_config() -> ([u8; 4], u8, [u8; 4]) {
-    unsafe {
-        (
-            STATIC_IPV4_ADDR_RUNTIME,
-            STATIC_IPV4_PREFIX_RUNTIME,
-            STATIC_IPV4_GATEWAY_RUNTIME,
-        )
-    }
+    let link = LINK.lock();
+    (link.static_ipv4_addr, link.static_ipv4_prefix, link.static_ipv4_gateway)
 }
 
 pub fn set_failover_policy_ethernet_first() {
-    unsafe {
-        FAILOVER_POLICY = FAILOVER_ETHERNET_FIRST;
-    }
+    LINK.lock().failover_policy = FAILOVER_ETHERNET_FIRST;
     refresh_active_transport();
 }
 
 pub fn set_failover_policy_wifi_first() {
-    unsafe {
-        FAILOVER_POLICY = FAILOVER_WIFI_FIRST;
-    }
+    LINK.lock().failover_policy = FAILOVER_WIFI_FIRST;
     refresh_active_transport();
 }
 
 pub fn set_https_mode_proxy() -> &'static str {
-    unsafe {
-        HTTPS_PROXY_ENABLED = true;
-    }
+    LINK.lock().https_proxy_enabled = true;
     "Compatibilidad HTTPS por proxy activada."
 }
 
 pub fn set_https_mode_disabled() -> &'static str {
-    unsafe {
-        HTTPS_PROXY_ENABLED = false;
-    }
+    LINK.lock().https_proxy_enabled = false;
     "Compatibilidad HTTPS por proxy desactivada."
 }
 
 pub fn set_dhcp_mode() -> &'static str {
+    LINK.lock().use_static_ipv4 = false;
     unsafe {
-        USE_STATIC_IPV4_RUNTIME = false;
-
         if let (Some(iface), Some(sockets), Some(dhcp_handle), Some(dns_handle)) =
-            (&mut IFACE, &mut SOCKETS, DHCP_HANDLE, DNS_HANDLE)
+            (IFACE.get_mut(), SOCKETS.get_mut(), *DHCP_HANDLE.get(), *DNS_HANDLE.get())
         {
             reset_ipv4_runtime(iface);
 
@@ -3777,12 +3332,12 @@ pub fn set_dhcp_mode() -> &'static str {
             update_dns_servers(sockets, dns_handle, &dns_servers);
 
             sockets.get_mut::<dhcpv4::Socket>(dhcp_handle).reset();
-            DHCP_LAST_RESET_TICK = crate::timer::ticks();
-            DHCP_STATUS = if ACTIVE_TRANSPORT == NET_TRANSPORT_NONE {
+            dhcp::on_deconfigured(crate::timer::ticks());
+            set_dhcp_status(if get_active_transport() == NET_TRANSPORT_NONE {
                 DHCP_STATUS_NO_LINK
             } else {
                 DHCP_STATUS_SEARCHING
-            };
+            });
             return "DHCP habilitado. Buscando IP...";
         }
     }
@@ -3794,27 +3349,31 @@ pub fn set_static_ipv4(ip: [u8; 4], prefix: u8, gateway: [u8; 4]) -> Result<&'st
         return Err("Prefijo invalido. Usa un valor entre 1 y 32.");
     }
 
+    {
+        let mut link = LINK.lock();
+        link.use_static_ipv4 = true;
+        link.static_ipv4_addr = ip;
+        link.static_ipv4_prefix = prefix;
+        link.static_ipv4_gateway = gateway;
+        link.static_dns_servers = STATIC_DNS_SERVERS;
+    }
     unsafe {
-        USE_STATIC_IPV4_RUNTIME = true;
-        STATIC_IPV4_ADDR_RUNTIME = ip;
-        STATIC_IPV4_PREFIX_RUNTIME = prefix;
-        STATIC_IPV4_GATEWAY_RUNTIME = gateway;
-        STATIC_DNS_SERVERS_RUNTIME = STATIC_DNS_SERVERS;
 
-        if let (Some(iface), Some(sockets), Some(dns_handle)) = (&mut IFACE, &mut SOCKETS, DNS_HANDLE) {
+        if let (Some(iface), Some(sockets), Some(dns_handle)) = (IFACE.get_mut(), SOCKETS.get_mut(), *DNS_HANDLE.get()) {
             apply_static_ipv4_runtime(iface);
 
             let dns_servers = static_dns_servers_runtime();
             update_dns_servers(sockets, dns_handle, &dns_servers);
-            DHCP_STATUS = DHCP_STATUS_STATIC;
+            set_dhcp_status(DHCP_STATUS_STATIC);
         }
 
-        if let (Some(sockets), Some(dhcp_handle)) = (&mut SOCKETS, DHCP_HANDLE) {
+        if let (Some(sockets), Some(dhcp_handle)) = (SOCKETS.get_mut(), *DHCP_HANDLE.get()) {
             sockets.get_mut::<dhcpv4::Socket>(dhcp_handle).reset();
-            DHCP_LAST_RESET_TICK = crate::timer::ticks();
+            dhcp::on_deconfigured(crate::timer::ticks());
         }
     }
 
+    portal::network_changed();
     Ok("IP fija aplicada.")
 }
 
@@ -3841,8 +3400,117 @@ pub fn set_static_ipv4_from_text(
     set_static_ipv4(ip, prefix, gateway)
 }
 
-pub fn get_packet_stats() -> (u64, u64) {
+pub fn get_dns_override() -> Vec<[u8; 4]> {
+    LINK.lock().dns_override.clone()
+}
+
+/// Force the DNS servers (empty goes back to the ones from DHCP or the
+/// static profile) and apply them right away.
+pub fn set_dns_override(servers: &[[u8; 4]]) {
+    LINK.lock().dns_override = servers.to_vec();
     unsafe {
-        (crate::intel_net::RX_COUNT, crate::intel_net::TX_COUNT)
+        if let (Some(sockets), Some(dns_handle)) = (SOCKETS.get_mut(), *DNS_HANDLE.get()) {
+            let dns_servers = if use_static_ipv4() || !servers.is_empty() {
+                static_dns_servers_runtime()
+            } else {
+                let lease_dns: Vec<IpAddress> =
+                    dhcp::lease_dns().iter().map(|ip| ipv4_from_octets(*ip).into()).collect();
+                if lease_dns.is_empty() {
+                    default_dns_servers()
+                } else {
+                    lease_dns
+                }
+            };
+            update_dns_servers(sockets, dns_handle, &dns_servers);
+        }
+    }
+}
+
+pub fn get_packet_stats() -> (u64, u64) {
+    (
+        crate::intel_net::RX_COUNT.load(core::sync::atomic::Ordering::Relaxed),
+        crate::intel_net::TX_COUNT.load(core::sync::atomic::Ordering::Relaxed),
+    )
+}
+
+crate::selftest::kernel_tests! {
+    "net";
+
+    fn http_headers_parse() {
+        let raw = b"HTTP/1.1 301 Moved Permanently\r\nLocation: https://example.com/\r\nContent-Length: 0\r\nSet-Cookie: a=1\r\nSet-Cookie: b=2\r\n\r\nbody";
+        let parsed = parse_http_headers(raw);
+        crate::selftest::ensure_eq(parsed.status_code, Some(301), "status")?;
+        crate::selftest::ensure_eq(&raw[parsed.body_offset..], &b"body"[..], "body_offset")?;
+        crate::selftest::ensure_eq(
+            header_first(parsed.headers.as_slice(), "LOCATION"),
+            Some("https://example.com/"),
+            "location",
+        )?;
+        crate::selftest::ensure_eq(header_values(parsed.headers.as_slice(), "set-cookie").len(), 2, "set-cookie")
+    }
+
+    fn http_chunked_body() {
+        let decoded = http_decode_chunked_body(b"4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n")
+            .ok_or_else(|| String::from("chunked invalido"))?;
+        crate::selftest::ensure_eq(decoded.as_slice(), &b"Wikipedia"[..], "chunked")
+    }
+
+    fn tcp_options_from_settings() {
+        let options = TcpOptions::from_settings(60, true);
+        crate::selftest::ensure_eq((options.keep_alive_ms, options.nodelay), (Some(60_000), true), "keep-alive")?;
+        crate::selftest::ensure_eq(TcpOptions::from_settings(0, false).keep_alive_ms, None, "sin keep-alive")?;
+        crate::selftest::ensure_eq((ms_to_ticks(2_500), ms_to_ticks(1)), (250, 1), "ticks")
+    }
+
+    fn url_parse() {
+        let (host, port, path) = parse_url("http://example.com:8080/a/b?c=1")
+            .ok_or_else(|| String::from("url invalida"))?;
+        crate::selftest::ensure_eq(host.as_str(), "example.com", "host")?;
+        crate::selftest::ensure_eq(port, 8080, "port")?;
+        crate::selftest::ensure_eq(path, "/a/b?c=1", "path")
+    }
+
+    fn hpack_decode_response_blocks() {
+        // RFC 7541 C.5.1 / C.5.2: literal headers with incremental indexing,
+        // then the same headers referenced from the dynamic table.
+        let mut first = Vec::new();
+        for (index, value) in [
+            (0x48u8, "302"),
+            (0x58, "private"),
+            (0x61, "Mon, 21 Oct 2013 20:13:21 GMT"),
+            (0x6E, "https://www.example.com"),
+        ] {
+            first.push(index);
+            redux_netparse::hpack::hpack_encode_string_no_huffman(&mut first, value);
+        }
+        let mut table = HpackDynamicTable::new();
+        let mut headers = Vec::new();
+        let mut status = None;
+        let ok = hpack_decode_header_block(first.as_slice(), &mut table, &mut headers, &mut status);
+        crate::selftest::ensure(ok, "bloque valido")?;
+        crate::selftest::ensure_eq(status, Some(302), "status")?;
+        crate::selftest::ensure_eq(headers.len(), 3, "cabeceras")?;
+        crate::selftest::ensure_eq(table.len(), 4, "tabla dinamica")?;
+
+        let mut second = alloc::vec![0x48u8];
+        redux_netparse::hpack::hpack_encode_string_no_huffman(&mut second, "307");
+        second.extend_from_slice(&[0xC1, 0xC0, 0xBF]);
+        let mut headers = Vec::new();
+        let mut status = None;
+        hpack_decode_header_block(second.as_slice(), &mut table, &mut headers, &mut status);
+        crate::selftest::ensure_eq(status, Some(307), "status indexado")?;
+        crate::selftest::ensure_eq(header_first(headers.as_slice(), "location"), Some("https://www.example.com"), "location")?;
+        crate::selftest::ensure_eq(header_first(headers.as_slice(), "cache-control"), Some("private"), "cache-control")
+    }
+
+    fn hpack_huffman_roundtrip() {
+        let text = "custom-value; q=0.9, www.example.com";
+        let mut encoded = Vec::new();
+        redux_netparse::hpack::hpack_encode_string_huffman(&mut encoded, text);
+        crate::selftest::ensure(encoded.first().map(|b| b & 0x80 != 0).unwrap_or(false), "bit H")?;
+        let mut idx = 0usize;
+        let decoded = redux_netparse::hpack::hpack_decode_string(encoded.as_slice(), &mut idx).ok_or_else(|| String::from("huffman invalido"))?;
+        crate::selftest::ensure_eq(decoded.as_str(), text, "huffman")?;
+        crate::selftest::ensure_eq(idx, encoded.len(), "longitud consumida")
     }
 }
@@ -0,0 +1,247 @@
+//! MTU per interface, jumbo frames included.
+//!
+//! Each NIC keeps its MTU in `net.mtu.<intel|virtio>` (1500 when unset). The
+//! Intel driver grows its RX buffers and turns on long packets past 1500;
+//! VirtIO takes up to what the device announces, or 9000 with mergeable RX
+//! buffers. smoltcp reads the MTU only when the interface is built, so a
+//! change rebuilds it on the next `net::poll`.
+//!
+//! A jumbo MTU makes smoltcp advertise an MSS that only the local segment
+//! carries. SYNs to addresses outside our subnet get their MSS clamped to a
+//! 1500-byte path on the way to the driver.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use redux_netparse::mss::{clamp_tcp_mss, ipv4_tcp_syn_destination, mss_for_mtu};
+use smoltcp::iface::Interface;
+
+use super::stats::Iface;
+use crate::config::ConfigValue;
+use crate::println;
+use crate::spinlock::SpinLock;
+
+pub const MTU_DEFAULT: usize = 1500;
+/// IPv6 needs at least 1280.
+pub const MTU_MIN: usize = 1280;
+pub const MTU_KEY_PREFIX: &str = "net.mtu.";
+/// Path MTU assumed past the gateway.
+const WAN_PATH_MTU: usize = 1500;
+
+static PENDING_REBUILD: AtomicBool = AtomicBool::new(false);
+/// Our IPv4 address and prefix, refreshed every poll for the MSS clamp.
+static LOCAL_NET: SpinLock<Option<([u8; 4], u8)>> = SpinLock::new(None);
+static CLAMPED_SYNS: AtomicU64 = AtomicU64::new(0);
+
+fn iface_name(iface: Iface) -> &'static str {
+    match iface {
+        Iface::Virtio => "virtio",
+        Iface::Intel => "intel",
+    }
+}
+
+fn parse_iface(text: &str) -> Option<Iface> {
+    if text.eq_ignore_ascii_case("intel") {
+        Some(Iface::Intel)
+    } else if text.eq_ignore_ascii_case("virtio") {
+        Some(Iface::Virtio)
+    } else {
+        None
+    }
+}
+
+fn key(iface: Iface) -> String {
+    alloc::format!("{}{}", MTU_KEY_PREFIX, iface_name(iface))
+}
+
+/// The NIC smoltcp runs on.
+fn active_iface() -> Iface {
+    if crate::intel_net::is_present() {
+        Iface::Intel
+    } else {
+        Iface::Virtio
+    }
+}
+
+/// Current MTU of `iface`, `None` when it is not present.
+pub fn current(iface: Iface) -> Option<usize> {
+    match iface {
+        Iface::Intel => crate::intel_net::is_present().then(crate::intel_net::mtu),
+        Iface::Virtio => crate::virtio::net::GLOBAL_NET.lock().as_ref().map(|drv| drv.mtu()),
+    }
+}
+
+pub fn max(iface: Iface) -> Option<usize> {
+    match iface {
+        Iface::Intel => crate::intel_net::is_present().then_some(crate::intel_net::MTU_MAX),
+        Iface::Virtio => crate::virtio::net::GLOBAL_NET.lock().as_ref().map(|drv| drv.max_mtu()),
+    }
+}
+
+fn set_driver(iface: Iface, mtu: usize) -> Result<(), &'static str> {
+    match iface {
+        Iface::Intel => crate::intel_net::set_mtu(mtu),
+        Iface::Virtio => match crate::virtio::net::GLOBAL_NET.lock().as_mut() {
+            Some(drv) => drv.set_mtu(mtu),
+            None => Err("sin dispositivo VirtIO"),
+        },
+    }
+}
+
+pub fn configured(iface: Iface) -> usize {
+    let value = crate::config::get_int(key(iface).as_str(), MTU_DEFAULT as i64);
+    if value < MTU_MIN as i64 {
+        MTU_DEFAULT
+    } else {
+        value as usize
+    }
+}
+
+/// Bring the drivers to their saved MTU; runs from `net::init` before the
+/// interface is built.
+pub fn apply_configured() {
+    for iface in [Iface::Intel, Iface::Virtio] {
+        let Some(current) = current(iface) else {
+            continue;
+        };
+        let wanted = configured(iface);
+        if wanted == current {
+            continue;
+        }
+        match set_driver(iface, wanted) {
+            Ok(()) => println(alloc::format!("Net: MTU {} -> {}", iface_name(iface), wanted).as_str()),
+            Err(err) => println(alloc::format!("Net: MTU {} {}: {}", iface_name(iface), wanted, err).as_str()),
+        }
+    }
+}
+
+/// Rebuild the interface after an MTU change; runs at the top of
+/// `net::poll`, outside the interface borrow.
+pub fn apply_pending() {
+    if PENDING_REBUILD.swap(false, Ordering::AcqRel) {
+        super::rebuild_interface();
+    }
+}
+
+pub(super) fn note_local_net(iface: &Interface) {
+    *LOCAL_NET.lock() = super::arp::local_ipv4(iface);
+}
+
+/// Clamp the MSS of a SYN leaving the subnet when the MTU is jumbo.
+pub(super) fn clamp_outgoing(iface: Iface, frame: &mut [u8]) {
+    if current(iface).unwrap_or(MTU_DEFAULT) <= WAN_PATH_MTU {
+        return;
+    }
+    let Some(dst) = ipv4_tcp_syn_destination(frame) else {
+        return;
+    };
+    let local_net = *LOCAL_NET.lock();
+    let on_link = local_net
+        .map(|(ip, prefix_len)| super::arp::same_subnet(dst, ip, prefix_len))
+        .unwrap_or(false);
+    if !on_link && clamp_tcp_mss(frame, mss_for_mtu(WAN_PATH_MTU)).is_some() {
+        CLAMPED_SYNS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+fn set_mtu(iface: Iface, mtu: Option<usize>) -> Result<usize, &'static str> {
+    let Some(limit) = max(iface) else {
+        return Err("interfaz no presente");
+    };
+    let value = mtu.unwrap_or(MTU_DEFAULT);
+    if !(MTU_MIN..=limit).contains(&value) {
+        return Err("MTU fuera de rango");
+    }
+    set_driver(iface, value)?;
+    match mtu {
+        Some(mtu) => crate::config::set(key(iface).as_str(), ConfigValue::Int(mtu as i64))?,
+        None => {
+            crate::config::unset(key(iface).as_str())?;
+        }
+    }
+    if iface == active_iface() {
+        PENDING_REBUILD.store(true, Ordering::Release);
+    }
+    Ok(value)
+}
+
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let (iface, value) = match parts.as_slice() {
+        [] => {
+            for iface in [Iface::Intel, Iface::Virtio] {
+                let (Some(current), Some(limit)) = (current(iface), max(iface)) else {
+                    continue;
+                };
+                out.push(alloc::format!(
+                    "net mtu: {} {} bytes (max {}, {}){}",
+                    iface_name(iface),
+                    current,
+                    limit,
+                    key(iface),
+                    if iface == active_iface() { " activa" } else { "" }
+                ));
+            }
+            if out.is_empty() {
+                out.push(String::from("net mtu: sin interfaces de red"));
+            }
+            out.push(alloc::format!(
+                "net mtu: SYN fuera de la subred con MSS recortado a {}: {}",
+                mss_for_mtu(WAN_PATH_MTU),
+                CLAMPED_SYNS.load(Ordering::Relaxed)
+            ));
+            return out;
+        }
+        [value] => (active_iface(), *value),
+        [name, value] => match parse_iface(name) {
+            Some(iface) => (iface, *value),
+            None => {
+                out.push(alloc::format!("net mtu: interfaz desconocida: {}", name));
+                return out;
+            }
+        },
+        _ => {
+            out.push(String::from("Uso: net mtu [[intel|virtio] <bytes>|default]"));
+            return out;
+        }
+    };
+    let mtu = if value.eq_ignore_ascii_case("default") {
+        None
+    } else {
+        match value.parse::<usize>() {
+            Ok(mtu) => Some(mtu),
+            Err(_) => {
+                out.push(String::from("Uso: net mtu [[intel|virtio] <bytes>|default]"));
+                return out;
+            }
+        }
+    };
+    match set_mtu(iface, mtu) {
+        Ok(mtu) => out.push(alloc::format!("net mtu: {} -> {} bytes", iface_name(iface), mtu)),
+        Err(err) => out.push(alloc::format!(
+            "net mtu: {} ({} a {})",
+            err,
+            MTU_MIN,
+            max(iface).unwrap_or(MTU_DEFAULT)
+        )),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "net_mtu";
+
+    fn keys_and_names() {
+        crate::selftest::ensure_eq(key(Iface::Intel).as_str(), "net.mtu.intel", "clave intel")?;
+        crate::selftest::ensure(crate::config::valid_key(key(Iface::Virtio).as_str()), "clave valida")?;
+        crate::selftest::ensure_eq(parse_iface("VirtIO"), Some(Iface::Virtio), "nombre")?;
+        crate::selftest::ensure_eq(parse_iface("eth0"), None, "desconocida")
+    }
+
+    fn wan_mss_fits_standard_path() {
+        crate::selftest::ensure_eq(mss_for_mtu(WAN_PATH_MTU), 1460, "MSS de 1500")?;
+        crate::selftest::ensure(mss_for_mtu(crate::intel_net::MTU_MAX) > 1460, "jumbo")
+    }
+}
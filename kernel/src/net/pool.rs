@@ -0,0 +1,361 @@
+//! Idle connection pool shared by every HTTP caller, keyed by origin
+//! (scheme, host, port, and whether it goes through the HTTPS proxy).
+//!
+//! An entry is either a plain HTTP/1.1 keep-alive socket or an HTTP/2
+//! session (TLS state plus stream bookkeeping). An HTTP/2 origin keeps a
+//! single session: every request to it, from any browser tab or program,
+//! opens the next stream on that connection instead of a new handshake.
+//! Requests run one at a time, so a connection is taken out while in use
+//! and put back when its response is complete.
+//!
+//! Limits come from `net.pool_max` (all origins), `net.pool_per_origin`
+//! (HTTP/1.1 sockets per origin) and `net.pool_idle_s`. Pooled sockets
+//! occupy slots of the fixed socket set, hence the low ceiling.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use smoltcp::iface::{SocketHandle, SocketSet};
+use smoltcp::socket::tcp;
+
+use super::Http2Session;
+use crate::spinlock::SpinLock;
+use crate::sync::KernelCell;
+
+pub const POOL_MAX_KEY: &str = "net.pool_max";
+pub const POOL_PER_ORIGIN_KEY: &str = "net.pool_per_origin";
+pub const POOL_IDLE_KEY: &str = "net.pool_idle_s";
+const POOL_MAX_DEFAULT: i64 = 4;
+const POOL_MAX_LIMIT: i64 = 6;
+const POOL_PER_ORIGIN_DEFAULT: i64 = 2;
+const POOL_PER_ORIGIN_LIMIT: i64 = 4;
+const POOL_IDLE_DEFAULT_S: i64 = 30;
+const POOL_IDLE_LIMIT_S: i64 = 300;
+/// Sessions are retired after this many streams so the per-stream
+/// bookkeeping of a long-lived connection stays bounded.
+pub const HTTP2_SESSION_MAX_STREAMS: usize = 256;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PoolLimits {
+    pub total: usize,
+    pub per_origin: usize,
+    pub idle_ticks: u64,
+}
+
+impl PoolLimits {
+    fn from_settings(total: i64, per_origin: i64, idle_s: i64) -> Self {
+        Self {
+            total: total.clamp(1, POOL_MAX_LIMIT) as usize,
+            per_origin: per_origin.clamp(1, POOL_PER_ORIGIN_LIMIT) as usize,
+            idle_ticks: super::ms_to_ticks(idle_s.clamp(1, POOL_IDLE_LIMIT_S) as u64 * 1000),
+        }
+    }
+
+    pub fn current() -> Self {
+        Self::from_settings(
+            crate::config::get_int(POOL_MAX_KEY, POOL_MAX_DEFAULT),
+            crate::config::get_int(POOL_PER_ORIGIN_KEY, POOL_PER_ORIGIN_DEFAULT),
+            crate::config::get_int(POOL_IDLE_KEY, POOL_IDLE_DEFAULT_S),
+        )
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Origin {
+    pub host: String,
+    pub port: u16,
+    pub is_https: bool,
+    pub via_proxy: bool,
+}
+
+impl Origin {
+    pub fn new(host: &str, port: u16, is_https: bool, via_proxy: bool) -> Self {
+        Self {
+            host: redux_netparse::ascii_lowercase(host),
+            port,
+            is_https,
+            via_proxy,
+        }
+    }
+
+    /// TLS is terminated here, not by the proxy.
+    fn is_direct_tls(&self) -> bool {
+        self.is_https && !self.via_proxy
+    }
+
+    fn label(&self) -> String {
+        alloc::format!(
+            "{}://{}:{}{}",
+            if self.is_https { "https" } else { "http" },
+            self.host,
+            self.port,
+            if self.via_proxy { " (proxy)" } else { "" }
+        )
+    }
+}
+
+pub enum PooledConn {
+    Http1,
+    Http2(Box<Http2Session>),
+}
+
+impl PooledConn {
+    fn kind(&self) -> &'static str {
+        match self {
+            PooledConn::Http1 => "http/1.1",
+            PooledConn::Http2(_) => "h2",
+        }
+    }
+}
+
+/// A connection taken out of the pool; `requests` already served on it.
+pub struct Checkout {
+    pub handle: SocketHandle,
+    pub conn: PooledConn,
+    pub requests: u32,
+}
+
+struct PoolEntry {
+    origin: Origin,
+    handle: SocketHandle,
+    conn: PooledConn,
+    requests: u32,
+    last_used_ticks: u64,
+}
+
+#[derive(Clone, Copy, Default)]
+struct PoolStats {
+    reused: u64,
+    reused_h2: u64,
+    stored: u64,
+    evicted: u64,
+    expired: u64,
+}
+
+// Entries own sockets of `super::SOCKETS`, so the pool follows the same
+// kernel-thread-only rule as the socket set.
+static POOL: KernelCell<Vec<PoolEntry>> = KernelCell::new(Vec::new());
+static POOL_STATS: SpinLock<PoolStats> = SpinLock::new(PoolStats {
+    reused: 0,
+    reused_h2: 0,
+    stored: 0,
+    evicted: 0,
+    expired: 0,
+});
+
+fn socket_reusable(sockets: &mut SocketSet<'_>, handle: SocketHandle) -> bool {
+    let socket = sockets.get_mut::<tcp::Socket>(handle);
+    socket.is_open() && socket.is_active() && socket.may_send()
+}
+
+/// Index of the least recently used entry among those `filter` accepts.
+fn oldest(entries: &[PoolEntry], filter: impl Fn(&PoolEntry) -> bool) -> Option<usize> {
+    entries
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| filter(e))
+        .min_by_key(|(_, e)| e.last_used_ticks)
+        .map(|(i, _)| i)
+}
+
+fn prune(sockets: &mut SocketSet<'_>, limits: &PoolLimits, now_ticks: u64) {
+    // SAFETY: kernel thread, and no other pool borrow is alive here.
+    let pool = unsafe { POOL.get_mut() };
+    let mut i = 0usize;
+    while i < pool.len() {
+        let stale = now_ticks.saturating_sub(pool[i].last_used_ticks) > limits.idle_ticks;
+        if stale || !socket_reusable(sockets, pool[i].handle) {
+            let removed = pool.remove(i);
+            sockets.remove(removed.handle);
+            POOL_STATS.lock().expired += 1;
+        } else {
+            i += 1;
+        }
+    }
+}
+
+/// Take an idle connection to `origin`. An HTTP/2 session wins over
+/// HTTP/1.1 sockets; with `http1` false only sessions are considered.
+/// Direct TLS origins only pool HTTP/2 sessions.
+pub fn take(sockets: &mut SocketSet<'_>, origin: &Origin, http1: bool, now_ticks: u64) -> Option<Checkout> {
+    let limits = PoolLimits::current();
+    prune(sockets, &limits, now_ticks);
+    // SAFETY: kernel thread; `prune` returned its borrow.
+    let pool = unsafe { POOL.get_mut() };
+    let idx = pool
+        .iter()
+        .position(|e| e.origin == *origin && matches!(e.conn, PooledConn::Http2(_)))
+        .or_else(|| {
+            if http1 && !origin.is_direct_tls() {
+                pool.iter().rposition(|e| e.origin == *origin)
+            } else {
+                None
+            }
+        })?;
+    let entry = pool.remove(idx);
+    {
+        let mut stats = POOL_STATS.lock();
+        stats.reused += 1;
+        if matches!(entry.conn, PooledConn::Http2(_)) {
+            stats.reused_h2 += 1;
+        }
+    }
+    Some(Checkout {
+        handle: entry.handle,
+        conn: entry.conn,
+        requests: entry.requests,
+    })
+}
+
+/// Return a connection after a complete response, or close it if it cannot
+/// be reused. Evicts the oldest connection of the same origin, then the
+/// oldest overall, to stay within the limits.
+pub fn store(
+    sockets: &mut SocketSet<'_>,
+    handle: SocketHandle,
+    origin: Origin,
+    conn: PooledConn,
+    requests: u32,
+    now_ticks: u64,
+) {
+    let usable = match &conn {
+        PooledConn::Http1 => !origin.is_direct_tls(),
+        PooledConn::Http2(session) => session.reusable(),
+    };
+    if !usable || !socket_reusable(sockets, handle) {
+        sockets.remove(handle);
+        return;
+    }
+
+    let limits = PoolLimits::current();
+    prune(sockets, &limits, now_ticks);
+    // SAFETY: kernel thread; `prune` returned its borrow.
+    let pool = unsafe { POOL.get_mut() };
+    let mut stats = POOL_STATS.lock();
+    // One session per HTTP/2 origin: the newer one replaces the rest.
+    let is_h2 = matches!(conn, PooledConn::Http2(_));
+    let per_origin = if is_h2 { 1 } else { limits.per_origin };
+    while pool.iter().filter(|e| e.origin == origin).count() >= per_origin {
+        let Some(i) = oldest(pool.as_slice(), |e| e.origin == origin) else {
+            break;
+        };
+        sockets.remove(pool.remove(i).handle);
+        stats.evicted += 1;
+    }
+    while pool.len() >= limits.total {
+        let Some(i) = oldest(pool.as_slice(), |_| true) else {
+            break;
+        };
+        sockets.remove(pool.remove(i).handle);
+        stats.evicted += 1;
+    }
+    pool.push(PoolEntry {
+        origin,
+        handle,
+        conn,
+        requests,
+        last_used_ticks: now_ticks,
+    });
+    stats.stored += 1;
+}
+
+/// Close every pooled connection.
+pub fn clear(sockets: &mut SocketSet<'_>) -> usize {
+    // SAFETY: kernel thread, and no other pool borrow is alive here.
+    let pool = unsafe { POOL.get_mut() };
+    let count = pool.len();
+    for entry in pool.drain(..) {
+        sockets.remove(entry.handle);
+    }
+    count
+}
+
+/// Shared implementation of `net pool [clear]`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let now = crate::timer::ticks();
+    let limits = PoolLimits::current();
+    if args.trim().eq_ignore_ascii_case("clear") {
+        let closed = unsafe { super::SOCKETS.get_mut().as_mut() }.map(clear).unwrap_or(0);
+        out.push(alloc::format!("net pool: {} conexiones cerradas", closed));
+        return out;
+    }
+    if !args.trim().is_empty() {
+        out.push(String::from("Uso: net pool [clear]"));
+        return out;
+    }
+
+    if let Some(sockets) = unsafe { super::SOCKETS.get_mut().as_mut() } {
+        prune(sockets, &limits, now);
+    }
+    // SAFETY: kernel thread; `prune` returned its borrow.
+    let pool = unsafe { POOL.get() };
+    out.push(alloc::format!(
+        "net pool: {} conexiones inactivas (max {}, {} por origen, {} s; {} / {} / {})",
+        pool.len(),
+        limits.total,
+        limits.per_origin,
+        limits.idle_ticks / 100,
+        POOL_MAX_KEY,
+        POOL_PER_ORIGIN_KEY,
+        POOL_IDLE_KEY
+    ));
+    for entry in pool.iter() {
+        let idle = now.saturating_sub(entry.last_used_ticks);
+        let streams = match &entry.conn {
+            PooledConn::Http2(session) => alloc::format!(", {} streams", session.streams_opened()),
+            PooledConn::Http1 => String::new(),
+        };
+        out.push(alloc::format!(
+            "  {}  {}  {} peticiones{}, inactiva {}.{} s",
+            entry.origin.label(),
+            entry.conn.kind(),
+            entry.requests,
+            streams,
+            idle / 100,
+            (idle % 100) / 10
+        ));
+    }
+    let stats = *POOL_STATS.lock();
+    out.push(alloc::format!(
+        "  Reutilizadas: {} (h2: {}), devueltas: {}, desalojadas: {}, caducadas: {}",
+        stats.reused,
+        stats.reused_h2,
+        stats.stored,
+        stats.evicted,
+        stats.expired
+    ));
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "net_pool";
+
+    fn limits_are_clamped() {
+        crate::selftest::ensure_eq(
+            PoolLimits::from_settings(POOL_MAX_DEFAULT, POOL_PER_ORIGIN_DEFAULT, POOL_IDLE_DEFAULT_S),
+            PoolLimits { total: 4, per_origin: 2, idle_ticks: 3_000 },
+            "por defecto",
+        )?;
+        crate::selftest::ensure_eq(
+            PoolLimits::from_settings(0, 0, 0),
+            PoolLimits { total: 1, per_origin: 1, idle_ticks: 100 },
+            "minimo",
+        )?;
+        crate::selftest::ensure_eq(
+            PoolLimits::from_settings(100, 100, 100_000),
+            PoolLimits { total: 6, per_origin: 4, idle_ticks: 30_000 },
+            "maximo",
+        )
+    }
+
+    fn origins_compare_by_scheme_host_and_port() {
+        let a = Origin::new("Example.COM", 443, true, false);
+        crate::selftest::ensure(a == Origin::new("example.com", 443, true, false), "host sin mayusculas")?;
+        crate::selftest::ensure(a != Origin::new("example.com", 80, false, false), "otro esquema")?;
+        crate::selftest::ensure(a != Origin::new("example.com", 443, true, true), "via proxy")?;
+        crate::selftest::ensure(a.is_direct_tls(), "tls directo")?;
+        crate::selftest::ensure_eq(a.label().as_str(), "https://example.com:443", "etiqueta")
+    }
+}
@@ -0,0 +1,311 @@
+//! Captive portal detection.
+//!
+//! Hotel and campus networks hand out an address and then answer every
+//! HTTP request with their login page. After each network change a plain
+//! HTTP probe (`net.portal_probe_url`, by default Google's `generate_204`)
+//! goes out through the background worker, awaited by an executor task. A
+//! `204` means the Internet is reachable; a redirect or any other answer
+//! means something in between rewrote it. In that case the user gets a
+//! notification and, unless `net.portal_open` is off, the portal page opens
+//! in a browser window. While behind a portal the probe repeats so the
+//! desktop notices the login going through.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use redux_netparse::http::{header_first, parse_http_headers};
+use redux_netparse::url::resolve_reference;
+
+use super::request::HttpRequest;
+use super::worker::RequestId;
+use crate::gui::notifications::{post, Urgency};
+use crate::spinlock::SpinLock;
+
+pub const PORTAL_CHECK_KEY: &str = "net.portal_check";
+pub const PORTAL_PROBE_URL_KEY: &str = "net.portal_probe_url";
+pub const PORTAL_OPEN_KEY: &str = "net.portal_open";
+const PORTAL_PROBE_URL_DEFAULT: &str = "http://connectivitycheck.gstatic.com/generate_204";
+/// Let DHCP, ARP and DNS settle before probing.
+const PORTAL_SETTLE_TICKS: u64 = 200;
+const PORTAL_RECHECK_TICKS: u64 = 1_500;
+const PORTAL_OFFLINE_RECHECK_TICKS: u64 = 6_000;
+const PORTAL_PROBE_TIMEOUT_MS: u64 = 10_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PortalState {
+    Unknown,
+    Checking,
+    Online,
+    Portal,
+    Offline,
+}
+
+impl PortalState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PortalState::Unknown => "sin comprobar",
+            PortalState::Checking => "comprobando...",
+            PortalState::Online => "acceso a Internet",
+            PortalState::Portal => "portal cautivo",
+            PortalState::Offline => "sin respuesta",
+        }
+    }
+}
+
+/// What a probe response says about the network.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ProbeVerdict {
+    Online,
+    /// Hijacked; the login page is at this URL.
+    Portal(String),
+}
+
+struct Portal {
+    state: PortalState,
+    url: Option<String>,
+    check_due_ticks: Option<u64>,
+    probe_id: Option<RequestId>,
+    last_check_ticks: u64,
+    /// Bumped on every network change; answers to older probes are dropped.
+    generation: u64,
+}
+
+static PORTAL: SpinLock<Portal> = SpinLock::new(Portal {
+    state: PortalState::Unknown,
+    url: None,
+    check_due_ticks: None,
+    probe_id: None,
+    last_check_ticks: 0,
+    generation: 0,
+});
+/// Portal page waiting for the compositor to open it.
+static OPEN_REQUEST: SpinLock<Option<String>> = SpinLock::new(None);
+
+fn probe_url() -> String {
+    crate::config::get_str(PORTAL_PROBE_URL_KEY, PORTAL_PROBE_URL_DEFAULT)
+}
+
+/// Classify the raw probe response. Only a `204`, or a `200` with an empty
+/// body, counts as a clean path to the Internet.
+pub fn classify_probe_response(probe_url: &str, response: &[u8]) -> ProbeVerdict {
+    let parsed = parse_http_headers(response);
+    let body_empty = response.len() <= parsed.body_offset;
+    match parsed.status_code {
+        Some(204) => ProbeVerdict::Online,
+        Some(200) if body_empty => ProbeVerdict::Online,
+        Some(301..=308) => {
+            let target = header_first(&parsed.headers, "location")
+                .map(|location| resolve_reference(probe_url, location))
+                .unwrap_or_else(|| String::from(probe_url));
+            ProbeVerdict::Portal(target)
+        }
+        _ => ProbeVerdict::Portal(String::from(probe_url)),
+    }
+}
+
+/// The network changed (new lease, static address, link up): probe again
+/// shortly.
+pub fn network_changed() {
+    let mut portal = PORTAL.lock();
+    portal.generation = portal.generation.wrapping_add(1);
+    portal.probe_id = None;
+    portal.check_due_ticks = Some(crate::timer::ticks() + PORTAL_SETTLE_TICKS);
+    portal.state = PortalState::Unknown;
+    portal.url = None;
+}
+
+/// Start a probe now (`net portal check`).
+pub fn check_now() -> bool {
+    if PORTAL.lock().probe_id.is_some() {
+        return true;
+    }
+    start_probe(crate::timer::ticks())
+}
+
+fn start_probe(now: u64) -> bool {
+    let url = probe_url();
+    let request = HttpRequest::get(url.as_str())
+        .header("Cache-Control", "no-cache")
+        .timeout_ms(PORTAL_PROBE_TIMEOUT_MS);
+    let generation = PORTAL.lock().generation;
+    let submitted = super::worker::fetch(request).and_then(|probe| {
+        let id = probe.id()?;
+        crate::executor::spawn("portal-probe", async move {
+            let response = probe.await;
+            finish_probe(generation, url.as_str(), response);
+        })?;
+        Some(id)
+    });
+    let mut portal = PORTAL.lock();
+    portal.check_due_ticks = None;
+    portal.last_check_ticks = now;
+    portal.probe_id = submitted;
+    if submitted.is_some() {
+        portal.state = PortalState::Checking;
+    }
+    submitted.is_some()
+}
+
+fn finish_probe(generation: u64, url: &str, response: Option<Vec<u8>>) {
+    let now = crate::timer::ticks();
+    {
+        let mut portal = PORTAL.lock();
+        if generation != portal.generation {
+            return;
+        }
+        portal.probe_id = None;
+        if response.is_none() {
+            portal.state = PortalState::Offline;
+            portal.check_due_ticks = Some(now + PORTAL_OFFLINE_RECHECK_TICKS);
+        }
+    }
+    let Some(response) = response else {
+        return;
+    };
+    match classify_probe_response(url, response.as_slice()) {
+        ProbeVerdict::Online => {
+            let was_portal = {
+                let mut portal = PORTAL.lock();
+                portal.state = PortalState::Online;
+                portal.url.take().is_some()
+            };
+            if was_portal {
+                post(
+                    "red",
+                    "Acceso a Internet",
+                    "Sesion del portal iniciada",
+                    Urgency::Success,
+                );
+            }
+        }
+        ProbeVerdict::Portal(target) => {
+            let first = PORTAL.lock().url.is_none();
+            crate::println(alloc::format!("Net: portal cautivo detectado -> {}", target).as_str());
+            if first {
+                let host = redux_netparse::url::extract_url_host(target.as_str()).unwrap_or("la red");
+                post(
+                    "red",
+                    "Portal cautivo",
+                    alloc::format!("Inicia sesion en {} para usar Internet", host).as_str(),
+                    Urgency::Warning,
+                );
+                if crate::config::get_bool(PORTAL_OPEN_KEY, true) {
+                    *OPEN_REQUEST.lock() = Some(target.clone());
+                }
+            }
+            let mut portal = PORTAL.lock();
+            portal.state = PortalState::Portal;
+            portal.url = Some(target);
+            portal.check_due_ticks = Some(now + PORTAL_RECHECK_TICKS);
+        }
+    }
+}
+
+/// Start a due probe; runs from `net::poll`.
+pub fn pump(now: u64) {
+    let due = PORTAL.lock().check_due_ticks;
+    match due {
+        Some(due) if now >= due => {}
+        _ => return,
+    }
+    if !crate::config::get_bool(PORTAL_CHECK_KEY, true) {
+        PORTAL.lock().check_due_ticks = None;
+        return;
+    }
+    if !start_probe(now) {
+        // Worker queue full; try again a bit later.
+        PORTAL.lock().check_due_ticks = Some(now + PORTAL_SETTLE_TICKS);
+    }
+}
+
+/// Portal page the desktop should open, if any.
+pub fn take_open_request() -> Option<String> {
+    OPEN_REQUEST.lock().take()
+}
+
+pub fn state() -> PortalState {
+    PORTAL.lock().state
+}
+
+pub fn portal_url() -> Option<String> {
+    PORTAL.lock().url.clone()
+}
+
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let args = args.trim();
+    if args.eq_ignore_ascii_case("check") {
+        if check_now() {
+            out.push(String::from("net portal: comprobando en segundo plano..."));
+        } else {
+            out.push(String::from("net portal: cola de peticiones llena"));
+        }
+        return out;
+    }
+    if args.eq_ignore_ascii_case("open") {
+        match portal_url() {
+            Some(url) => {
+                *OPEN_REQUEST.lock() = Some(url.clone());
+                out.push(alloc::format!("net portal: abriendo {}", url));
+            }
+            None => out.push(String::from("net portal: no hay portal cautivo detectado")),
+        }
+        return out;
+    }
+    if !args.is_empty() {
+        out.push(String::from("Uso: net portal [check|open]"));
+        return out;
+    }
+    out.push(alloc::format!("net portal: {}", state().as_str()));
+    if let Some(url) = portal_url() {
+        out.push(alloc::format!("net portal: pagina de acceso -> {}", url));
+    }
+    let last = PORTAL.lock().last_check_ticks;
+    if last != 0 {
+        out.push(alloc::format!(
+            "net portal: ultima comprobacion hace {} s",
+            crate::timer::ticks().saturating_sub(last) / 100
+        ));
+    }
+    out.push(alloc::format!(
+        "net portal: sonda {} ({} {}, {} {})",
+        probe_url(),
+        PORTAL_CHECK_KEY,
+        crate::config::get_bool(PORTAL_CHECK_KEY, true),
+        PORTAL_OPEN_KEY,
+        crate::config::get_bool(PORTAL_OPEN_KEY, true)
+    ));
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "net_portal";
+
+    fn probe_204_is_online() {
+        let url = PORTAL_PROBE_URL_DEFAULT;
+        crate::selftest::ensure_eq(
+            classify_probe_response(url, b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n"),
+            ProbeVerdict::Online,
+            "204",
+        )?;
+        crate::selftest::ensure_eq(
+            classify_probe_response(url, b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n"),
+            ProbeVerdict::Online,
+            "200 vacio",
+        )
+    }
+
+    fn hijacked_probe_is_portal() {
+        let url = "http://connectivitycheck.gstatic.com/generate_204";
+        crate::selftest::ensure_eq(
+            classify_probe_response(url, b"HTTP/1.1 302 Found\r\nLocation: http://10.0.0.1/login?x=1\r\n\r\n"),
+            ProbeVerdict::Portal(String::from("http://10.0.0.1/login?x=1")),
+            "redireccion",
+        )?;
+        crate::selftest::ensure_eq(
+            classify_probe_response(url, b"HTTP/1.1 200 OK\r\n\r\n<html>login</html>"),
+            ProbeVerdict::Portal(String::from(url)),
+            "pagina reescrita",
+        )
+    }
+}
@@ -0,0 +1,453 @@
+//! Named network profiles kept in the config store.
+//!
+//! A profile is `net.profile.<name>`: addressing (DHCP or a static
+//! address), DNS servers that override DHCP's and whether HTTPS goes
+//! through the compatibility proxy. `net.iface.<id>` binds an interface
+//! (its MAC in hex, or `wifi`) to a profile, which is applied whenever
+//! that interface becomes the active transport, so a machine keeps its
+//! addressing across reboots and cable swaps.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::config::ConfigValue;
+use crate::println;
+use crate::spinlock::SpinLock;
+
+pub const PROFILE_KEY_PREFIX: &str = "net.profile.";
+pub const IFACE_KEY_PREFIX: &str = "net.iface.";
+const PROFILE_NAME_MAX: usize = 32;
+const PROFILE_DNS_MAX: usize = 2;
+const WIFI_INTERFACE_ID: &str = "wifi";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ProfileMode {
+    Dhcp,
+    Static {
+        address: [u8; 4],
+        prefix_len: u8,
+        gateway: [u8; 4],
+    },
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct NetProfile {
+    pub mode: ProfileMode,
+    /// Empty keeps the servers from DHCP or the static defaults.
+    pub dns: Vec<[u8; 4]>,
+    pub https_proxy: bool,
+}
+
+static PENDING_LINK: AtomicBool = AtomicBool::new(false);
+static ACTIVE_PROFILE: SpinLock<Option<String>> = SpinLock::new(None);
+
+fn ip_text(ip: &[u8; 4]) -> String {
+    alloc::format!("{}.{}.{}.{}", ip[0], ip[1], ip[2], ip[3])
+}
+
+fn dns_text(dns: &[[u8; 4]]) -> String {
+    let list: Vec<String> = dns.iter().map(ip_text).collect();
+    list.join(",")
+}
+
+fn parse_dns_list(text: &str) -> Option<Vec<[u8; 4]>> {
+    let mut out = Vec::new();
+    for part in text.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        if out.len() >= PROFILE_DNS_MAX {
+            return None;
+        }
+        out.push(super::parse_ipv4_octets(part)?);
+    }
+    Some(out)
+}
+
+impl NetProfile {
+    /// `dhcp;;;dns,dns;proxy` or `static;addr/prefix;gateway;dns,dns;direct`.
+    pub fn to_text(&self) -> String {
+        let (mode, address, gateway) = match self.mode {
+            ProfileMode::Dhcp => ("dhcp", String::new(), String::new()),
+            ProfileMode::Static {
+                address,
+                prefix_len,
+                gateway,
+            } => (
+                "static",
+                alloc::format!("{}/{}", ip_text(&address), prefix_len),
+                ip_text(&gateway),
+            ),
+        };
+        alloc::format!(
+            "{};{};{};{};{}",
+            mode,
+            address,
+            gateway,
+            dns_text(&self.dns),
+            if self.https_proxy { "proxy" } else { "direct" }
+        )
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut fields = text.split(';');
+        let mode = fields.next()?;
+        let address = fields.next()?;
+        let gateway = fields.next()?;
+        let dns = parse_dns_list(fields.next()?)?;
+        let https_proxy = match fields.next()? {
+            "proxy" => true,
+            "direct" => false,
+            _ => return None,
+        };
+        if fields.next().is_some() {
+            return None;
+        }
+        let mode = match mode {
+            "dhcp" => ProfileMode::Dhcp,
+            "static" => {
+                let (ip, prefix) = address.split_once('/')?;
+                let prefix_len = prefix.parse::<u8>().ok().filter(|p| *p > 0 && *p <= 32)?;
+                ProfileMode::Static {
+                    address: super::parse_ipv4_octets(ip)?,
+                    prefix_len,
+                    gateway: super::parse_ipv4_octets(gateway)?,
+                }
+            }
+            _ => return None,
+        };
+        Some(Self { mode, dns, https_proxy })
+    }
+
+    /// The settings in effect right now.
+    pub fn current() -> Self {
+        let mode = if super::get_network_mode() == super::NET_MODE_STATIC {
+            let (address, prefix_len, gateway) = super::get_static_ipv4_config();
+            ProfileMode::Static {
+                address,
+                prefix_len,
+                gateway,
+            }
+        } else {
+            ProfileMode::Dhcp
+        };
+        Self {
+            mode,
+            dns: super::get_dns_override(),
+            https_proxy: super::is_https_proxy_enabled(),
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let mut out = match self.mode {
+            ProfileMode::Dhcp => String::from("DHCP"),
+            ProfileMode::Static {
+                address,
+                prefix_len,
+                gateway,
+            } => alloc::format!("fija {}/{} gw {}", ip_text(&address), prefix_len, ip_text(&gateway)),
+        };
+        if !self.dns.is_empty() {
+            out.push_str(alloc::format!(", DNS {}", dns_text(&self.dns)).as_str());
+        }
+        if self.https_proxy {
+            out.push_str(", proxy HTTPS");
+        }
+        out
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= PROFILE_NAME_MAX
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_' || b == b'-')
+}
+
+fn hex_id(mac: [u8; 6]) -> String {
+    let mut out = String::with_capacity(12);
+    for byte in mac.iter() {
+        out.push_str(alloc::format!("{:02x}", byte).as_str());
+    }
+    out
+}
+
+/// Id of the active interface: its MAC for Ethernet, `wifi` for WiFi.
+pub fn interface_id() -> Option<String> {
+    let transport = super::get_active_transport();
+    if transport == super::NET_TRANSPORT_INTEL_WIFI {
+        return Some(String::from(WIFI_INTERFACE_ID));
+    }
+    let mac = if transport == super::NET_TRANSPORT_INTEL_ETH {
+        crate::intel_net::get_mac_address()
+    } else if transport == super::NET_TRANSPORT_VIRTIO {
+        crate::virtio::net::mac_address()
+    } else {
+        None
+    };
+    mac.map(hex_id)
+}
+
+pub fn load(name: &str) -> Option<NetProfile> {
+    match crate::config::get(alloc::format!("{}{}", PROFILE_KEY_PREFIX, name).as_str()) {
+        Some(ConfigValue::Str(text)) => NetProfile::parse(text.as_str()),
+        _ => None,
+    }
+}
+
+fn bound_profile(interface: &str) -> Option<String> {
+    match crate::config::get(alloc::format!("{}{}", IFACE_KEY_PREFIX, interface).as_str()) {
+        Some(ConfigValue::Str(name)) => Some(name),
+        _ => None,
+    }
+}
+
+fn bind(interface: &str, name: &str) -> Result<(), &'static str> {
+    crate::config::set(
+        alloc::format!("{}{}", IFACE_KEY_PREFIX, interface).as_str(),
+        ConfigValue::Str(String::from(name)),
+    )
+}
+
+/// Apply a profile, leaving alone what already matches so a DHCP lease
+/// survives re-applying a DHCP profile.
+pub fn apply(profile: &NetProfile) -> Result<(), &'static str> {
+    match profile.mode {
+        ProfileMode::Dhcp => {
+            if super::get_network_mode() != super::NET_MODE_DHCP {
+                super::set_dhcp_mode();
+            }
+        }
+        ProfileMode::Static {
+            address,
+            prefix_len,
+            gateway,
+        } => {
+            if super::get_network_mode() != super::NET_MODE_STATIC
+                || super::get_static_ipv4_config() != (address, prefix_len, gateway)
+            {
+                super::set_static_ipv4(address, prefix_len, gateway)?;
+            }
+        }
+    }
+    super::set_dns_override(&profile.dns);
+    if profile.https_proxy {
+        super::set_https_mode_proxy();
+    } else {
+        super::set_https_mode_disabled();
+    }
+    Ok(())
+}
+
+/// Called when the active transport changes; the profile is applied from
+/// the next `net::poll`, outside the interface borrow.
+pub fn link_changed() {
+    PENDING_LINK.store(true, Ordering::Release);
+}
+
+pub fn apply_pending() {
+    let pending = PENDING_LINK.swap(false, Ordering::AcqRel);
+    if !pending {
+        return;
+    }
+    let Some(interface) = interface_id() else {
+        return;
+    };
+    let Some(name) = bound_profile(interface.as_str()) else {
+        return;
+    };
+    let Some(profile) = load(name.as_str()) else {
+        println(alloc::format!("Net: perfil {} no encontrado para {}.", name, interface).as_str());
+        return;
+    };
+    match apply(&profile) {
+        Ok(()) => {
+            println(alloc::format!("Net: perfil {} aplicado a {} ({}).", name, interface, profile.summary()).as_str());
+            *ACTIVE_PROFILE.lock() = Some(name);
+        }
+        Err(err) => println(alloc::format!("Net: perfil {}: {}", name, err).as_str()),
+    }
+}
+
+/// Profile lines for `net`.
+pub fn status_lines() -> Vec<String> {
+    let mut out = Vec::new();
+    if let Some(name) = ACTIVE_PROFILE.lock().as_ref() {
+        out.push(alloc::format!("Net: perfil -> {}", name));
+    }
+    let dns = super::get_dns_override();
+    if !dns.is_empty() {
+        out.push(alloc::format!("Net: DNS del perfil -> {}", dns_text(&dns)));
+    }
+    out
+}
+
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut parts = args.split_whitespace();
+    let sub = parts.next().unwrap_or("list");
+    let name = parts.next().unwrap_or("");
+    let interface = interface_id();
+
+    if sub.eq_ignore_ascii_case("list") {
+        let profiles = crate::config::list(PROFILE_KEY_PREFIX);
+        let bindings = crate::config::list(IFACE_KEY_PREFIX);
+        out.push(alloc::format!(
+            "net profile: {} perfiles, interfaz activa {}",
+            profiles.len(),
+            interface.as_deref().unwrap_or("(ninguna)")
+        ));
+        for (key, value) in profiles.iter() {
+            let profile_name = &key[PROFILE_KEY_PREFIX.len()..];
+            let summary = match value {
+                ConfigValue::Str(text) => NetProfile::parse(text.as_str())
+                    .map(|profile| profile.summary())
+                    .unwrap_or_else(|| String::from("(invalido)")),
+                _ => String::from("(invalido)"),
+            };
+            let bound: Vec<&str> = bindings
+                .iter()
+                .filter(|(_, value)| matches!(value, ConfigValue::Str(bound) if bound == profile_name))
+                .map(|(key, _)| &key[IFACE_KEY_PREFIX.len()..])
+                .collect();
+            let active = ACTIVE_PROFILE.lock().as_deref() == Some(profile_name);
+            out.push(alloc::format!(
+                "{} {}  {}{}",
+                if active { "*" } else { " " },
+                profile_name,
+                summary,
+                if bound.is_empty() {
+                    String::new()
+                } else {
+                    alloc::format!("  [{}]", bound.join(", "))
+                }
+            ));
+        }
+        return out;
+    }
+
+    if !valid_name(name) {
+        out.push(String::from(
+            "Uso: net profile [list|save <nombre> [dns <ip>[,<ip>]]|load <nombre>|delete <nombre>]",
+        ));
+        out.push(String::from(
+            "net profile: nombres con a-z 0-9 _ -, hasta 32 caracteres",
+        ));
+        return out;
+    }
+    let key = alloc::format!("{}{}", PROFILE_KEY_PREFIX, name);
+
+    if sub.eq_ignore_ascii_case("save") {
+        let mut profile = NetProfile::current();
+        match (parts.next(), parts.next()) {
+            (None, _) => {}
+            (Some(word), Some(list)) if word.eq_ignore_ascii_case("dns") => match parse_dns_list(list) {
+                Some(dns) => profile.dns = dns,
+                None => {
+                    out.push(String::from(
+                        "net profile: lista DNS invalida (hasta 2 IPs separadas por comas)",
+                    ));
+                    return out;
+                }
+            },
+            _ => {
+                out.push(String::from("Uso: net profile save <nombre> [dns <ip>[,<ip>]]"));
+                return out;
+            }
+        }
+        if let Err(err) = crate::config::set(key.as_str(), ConfigValue::Str(profile.to_text())) {
+            out.push(alloc::format!("net profile: {}", err));
+            return out;
+        }
+        out.push(alloc::format!("net profile: {} guardado ({})", name, profile.summary()));
+        if let Some(interface) = interface.as_deref() {
+            match bind(interface, name) {
+                Ok(()) => out.push(alloc::format!("net profile: se aplicara al conectar {}", interface)),
+                Err(err) => out.push(alloc::format!("net profile: {}", err)),
+            }
+        }
+        if profile.dns != super::get_dns_override() {
+            super::set_dns_override(&profile.dns);
+        }
+        *ACTIVE_PROFILE.lock() = Some(String::from(name));
+        return out;
+    }
+
+    if sub.eq_ignore_ascii_case("load") {
+        let Some(profile) = load(name) else {
+            out.push(alloc::format!("net profile: {} no existe", name));
+            return out;
+        };
+        if let Err(err) = apply(&profile) {
+            out.push(alloc::format!("net profile: {}", err));
+            return out;
+        }
+        out.push(alloc::format!("net profile: {} aplicado ({})", name, profile.summary()));
+        if let Some(interface) = interface.as_deref() {
+            if bind(interface, name).is_ok() {
+                out.push(alloc::format!("net profile: se aplicara al conectar {}", interface));
+            }
+        }
+        *ACTIVE_PROFILE.lock() = Some(String::from(name));
+        return out;
+    }
+
+    if sub.eq_ignore_ascii_case("delete") {
+        match crate::config::unset(key.as_str()) {
+            Ok(true) => {}
+            Ok(false) => {
+                out.push(alloc::format!("net profile: {} no existe", name));
+                return out;
+            }
+            Err(err) => {
+                out.push(alloc::format!("net profile: {}", err));
+                return out;
+            }
+        }
+        for (bind_key, value) in crate::config::list(IFACE_KEY_PREFIX) {
+            if matches!(value, ConfigValue::Str(bound) if bound == name) {
+                let _ = crate::config::unset(bind_key.as_str());
+            }
+        }
+        {
+            let mut active = ACTIVE_PROFILE.lock();
+            if active.as_deref() == Some(name) {
+                *active = None;
+            }
+        }
+        out.push(alloc::format!("net profile: {} borrado", name));
+        return out;
+    }
+
+    out.push(String::from(
+        "Uso: net profile [list|save <nombre> [dns <ip>[,<ip>]]|load <nombre>|delete <nombre>]",
+    ));
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "net_profile";
+
+    fn profile_text_roundtrip() {
+        let static_profile = NetProfile {
+            mode: ProfileMode::Static {
+                address: [10, 0, 5, 20],
+                prefix_len: 24,
+                gateway: [10, 0, 5, 1],
+            },
+            dns: alloc::vec![[10, 0, 5, 1], [1, 1, 1, 1]],
+            https_proxy: false,
+        };
+        let text = static_profile.to_text();
+        crate::selftest::ensure_eq(text.as_str(), "static;10.0.5.20/24;10.0.5.1;10.0.5.1,1.1.1.1;direct", "texto")?;
+        crate::selftest::ensure_eq(NetProfile::parse(text.as_str()), Some(static_profile), "static")?;
+        let dhcp_profile = NetProfile { mode: ProfileMode::Dhcp, dns: Vec::new(), https_proxy: true };
+        crate::selftest::ensure_eq(NetProfile::parse(dhcp_profile.to_text().as_str()), Some(dhcp_profile), "dhcp")
+    }
+
+    fn profile_rejects_bad_text() {
+        crate::selftest::ensure(NetProfile::parse("static;10.0.0.1/33;10.0.0.254;;direct").is_none(), "prefijo")?;
+        crate::selftest::ensure(NetProfile::parse("dhcp;;;1.1.1.1,8.8.8.8,9.9.9.9;direct").is_none(), "tres DNS")?;
+        crate::selftest::ensure(NetProfile::parse("dhcp;;;;direct;extra").is_none(), "campo extra")?;
+        crate::selftest::ensure(valid_name("lab-2") && !valid_name("Lab") && !valid_name("a.b"), "nombres")
+    }
+}
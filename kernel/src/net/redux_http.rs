@@ -0,0 +1,380 @@
+//! Userspace HTTP client API behind the `SYS_HTTP_*` syscalls and the
+//! ReduxLang `http_*` builtins.
+//!
+//! A request is a handle: `open` it with a method and URL, add headers, `send`
+//! it, then pull the response headers and stream the body with `read`. Every
+//! handle belongs to the caller that opened it (thread name, or "reduxlang"),
+//! the destination has to pass `firewall::allows`, and downloaded bytes are
+//! charged to the caller with `quota::charge_net`; `net::stats` counts the
+//! bytes sent and received per caller. Before `send`, a handle can set
+//! options (`set_option`): keep-alive interval, no-delay, a timeout that
+//! bounds connecting and each wait for data, and how many redirects to
+//! follow (none by default, so callers see the 3xx).
+//!
+//! Any method from `HttpMethod` can be sent; `send` takes the request body,
+//! which GET and HEAD may not have.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use redux_netparse::http::parse_http_headers;
+use redux_netparse::url::parse_url;
+
+use super::request::{HttpMethod, HttpRequest, RedirectPolicy};
+use super::{firewall, TcpOptions};
+use crate::sync::KernelCell;
+
+const REDUX_HTTP_MAX_HANDLES: usize = 8;
+const REDUX_HTTP_MAX_HEADERS: usize = 32;
+const REDUX_HTTP_MAX_URL: usize = 2048;
+const REDUX_HTTP_MAX_HEADER_LEN: usize = 1024;
+const REDUX_HTTP_TIMEOUT_MS: u64 = 50_000;
+/// Longest keep-alive interval or timeout a caller may ask for: one hour.
+const REDUX_HTTP_MAX_OPTION_MS: u64 = 3_600_000;
+const REDUX_HTTP_MAX_REDIRECTS: u64 = 10;
+pub const REDUX_HTTP_MAX_BODY: usize = 1024 * 1024;
+
+/// Headers the kernel client sets itself; callers may not override them.
+const REDUX_HTTP_RESERVED_HEADERS: [&str; 5] =
+    ["host", "connection", "content-length", "transfer-encoding", "upgrade"];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HttpApiError {
+    BadHandle,
+    Invalid,
+    Unsupported,
+    Denied,
+    Quota,
+    Network,
+    Busy,
+}
+
+impl HttpApiError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HttpApiError::BadHandle => "bad handle",
+            HttpApiError::Invalid => "invalid request",
+            HttpApiError::Unsupported => "unsupported method",
+            HttpApiError::Denied => "blocked by firewall",
+            HttpApiError::Quota => "network quota exceeded",
+            HttpApiError::Network => "network error",
+            HttpApiError::Busy => "too many open requests",
+        }
+    }
+}
+
+/// Socket options for `set_option`; the numbers are the syscall ABI.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HttpOption {
+    /// Milliseconds between keep-alive probes; 0 turns them off.
+    KeepAliveMs = 1,
+    /// 1 disables Nagle, 0 enables it.
+    NoDelay = 2,
+    /// Milliseconds to connect and between received bytes; 0 restores the
+    /// default.
+    TimeoutMs = 3,
+    /// Redirects to follow, up to 10; 0 returns them to the caller.
+    Redirects = 4,
+}
+
+impl HttpOption {
+    pub fn from_raw(raw: u64) -> Option<Self> {
+        match raw {
+            1 => Some(HttpOption::KeepAliveMs),
+            2 => Some(HttpOption::NoDelay),
+            3 => Some(HttpOption::TimeoutMs),
+            4 => Some(HttpOption::Redirects),
+            _ => None,
+        }
+    }
+}
+
+/// Apply one option to `options` / `timeout_ms` / `redirects`.
+fn apply_option(
+    options: &mut TcpOptions,
+    timeout_ms: &mut u64,
+    redirects: &mut usize,
+    option: HttpOption,
+    value: u64,
+) -> Result<(), HttpApiError> {
+    match option {
+        HttpOption::NoDelay if value > 1 => return Err(HttpApiError::Invalid),
+        HttpOption::NoDelay => options.nodelay = value == 1,
+        HttpOption::Redirects if value > REDUX_HTTP_MAX_REDIRECTS => return Err(HttpApiError::Invalid),
+        HttpOption::Redirects => *redirects = value as usize,
+        _ if value > REDUX_HTTP_MAX_OPTION_MS => return Err(HttpApiError::Invalid),
+        HttpOption::KeepAliveMs => options.keep_alive_ms = (value > 0).then_some(value),
+        HttpOption::TimeoutMs if value == 0 => {
+            options.user_timeout_ms = None;
+            *timeout_ms = REDUX_HTTP_TIMEOUT_MS;
+        }
+        HttpOption::TimeoutMs => {
+            options.user_timeout_ms = Some(value);
+            *timeout_ms = value;
+        }
+    }
+    Ok(())
+}
+
+struct HttpResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+struct HttpHandle {
+    id: u32,
+    owner: String,
+    method: HttpMethod,
+    url: String,
+    headers: Vec<(String, String)>,
+    options: TcpOptions,
+    timeout_ms: u64,
+    redirects: usize,
+    response: Option<HttpResponse>,
+    read_pos: usize,
+}
+
+// A handle stays borrowed while `send` drives the network stack, so this
+// cannot be a lock; handles are only used from the kernel thread.
+static HTTP_HANDLES: KernelCell<Vec<HttpHandle>> = KernelCell::new(Vec::new());
+static HTTP_NEXT_HANDLE: AtomicU32 = AtomicU32::new(1);
+
+fn handle_mut(id: u32, owner: &str) -> Result<&'static mut HttpHandle, HttpApiError> {
+    // SAFETY: kernel thread; each API call borrows a single handle at a time.
+    unsafe { HTTP_HANDLES.get_mut() }
+        .iter_mut()
+        .find(|h| h.id == id && h.owner == owner)
+        .ok_or(HttpApiError::BadHandle)
+}
+
+fn parse_method(method: &str) -> Result<HttpMethod, HttpApiError> {
+    let method = method.trim();
+    if let Some(parsed) = HttpMethod::parse(method) {
+        Ok(parsed)
+    } else if ["CONNECT", "TRACE"].iter().any(|m| method.eq_ignore_ascii_case(m)) {
+        Err(HttpApiError::Unsupported)
+    } else {
+        Err(HttpApiError::Invalid)
+    }
+}
+
+/// Create a request handle. The URL is checked against the firewall here so
+/// a blocked destination fails before any header is set.
+pub fn open(owner: &str, method: &str, url: &str) -> Result<u32, HttpApiError> {
+    let method = parse_method(method)?;
+    let url = url.trim();
+    if url.is_empty() || url.len() > REDUX_HTTP_MAX_URL || url.bytes().any(|b| b <= b' ') {
+        return Err(HttpApiError::Invalid);
+    }
+    let (host, port, _) = parse_url(url).ok_or(HttpApiError::Invalid)?;
+    if !firewall::allows(host.as_str(), port) {
+        return Err(HttpApiError::Denied);
+    }
+    // SAFETY: kernel thread, and no handle borrow is alive here.
+    let handles = unsafe { HTTP_HANDLES.get_mut() };
+    if handles.iter().filter(|h| h.owner == owner).count() >= REDUX_HTTP_MAX_HANDLES {
+        return Err(HttpApiError::Busy);
+    }
+    let id = HTTP_NEXT_HANDLE
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |id| {
+            Some(id.wrapping_add(1).max(1))
+        })
+        .unwrap_or(1);
+    handles.push(HttpHandle {
+        id,
+        owner: String::from(owner),
+        method,
+        url: String::from(url),
+        headers: Vec::new(),
+        options: TcpOptions::defaults(),
+        timeout_ms: REDUX_HTTP_TIMEOUT_MS,
+        redirects: 0,
+        response: None,
+        read_pos: 0,
+    });
+    Ok(id)
+}
+
+pub fn set_header(id: u32, owner: &str, name: &str, value: &str) -> Result<(), HttpApiError> {
+    let handle = handle_mut(id, owner)?;
+    if handle.response.is_some() || handle.headers.len() >= REDUX_HTTP_MAX_HEADERS {
+        return Err(HttpApiError::Invalid);
+    }
+    let name = name.trim();
+    let value = value.trim();
+    let name_ok = !name.is_empty()
+        && name.bytes().all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b));
+    let value_ok = value.bytes().all(|b| b != b'\r' && b != b'\n' && b != 0);
+    if !name_ok || !value_ok || name.len() + value.len() > REDUX_HTTP_MAX_HEADER_LEN {
+        return Err(HttpApiError::Invalid);
+    }
+    if REDUX_HTTP_RESERVED_HEADERS.iter().any(|r| name.eq_ignore_ascii_case(r)) {
+        return Err(HttpApiError::Invalid);
+    }
+    handle.headers.push((String::from(name), String::from(value)));
+    Ok(())
+}
+
+/// Set an option for the request; only before `send`.
+pub fn set_option(id: u32, owner: &str, option: HttpOption, value: u64) -> Result<(), HttpApiError> {
+    let handle = handle_mut(id, owner)?;
+    if handle.response.is_some() {
+        return Err(HttpApiError::Invalid);
+    }
+    apply_option(&mut handle.options, &mut handle.timeout_ms, &mut handle.redirects, option, value)
+}
+
+/// Perform the request and buffer the response. Returns the status code.
+pub fn send(id: u32, owner: &str, body: &[u8]) -> Result<u16, HttpApiError> {
+    let handle = handle_mut(id, owner)?;
+    if handle.response.is_some() {
+        return Err(HttpApiError::Invalid);
+    }
+    let bodyless = matches!(handle.method, HttpMethod::Get | HttpMethod::Head);
+    if (bodyless && !body.is_empty()) || body.len() > REDUX_HTTP_MAX_BODY {
+        return Err(HttpApiError::Invalid);
+    }
+
+    let mut request = HttpRequest::new(handle.method, handle.url.as_str())
+        .timeout_ms(handle.timeout_ms)
+        .options(handle.options);
+    if handle.redirects > 0 {
+        request = request.redirects(RedirectPolicy::Follow(handle.redirects));
+    }
+    request.headers = handle.headers.clone();
+    request.body = body.to_vec();
+    // Request line, caller headers and body; what the kernel adds is not charged.
+    let sent = handle.url.len()
+        + handle.headers.iter().map(|(name, value)| name.len() + value.len() + 4).sum::<usize>()
+        + body.len();
+    let raw = request.send(&mut || {}).ok_or(HttpApiError::Network)?;
+    super::stats::charge_process(owner, raw.len() as u64, sent as u64);
+    let parsed = parse_http_headers(raw.as_slice());
+    let status = parsed.status_code.ok_or(HttpApiError::Network)?;
+    let body = if handle.method == HttpMethod::Head {
+        Vec::new()
+    } else {
+        raw.get(parsed.body_offset..).unwrap_or(&[]).to_vec()
+    };
+    if !crate::quota::charge_net(owner, raw.len() as u64) {
+        return Err(HttpApiError::Quota);
+    }
+
+    handle.response = Some(HttpResponse {
+        status,
+        headers: parsed.headers,
+        body,
+    });
+    handle.read_pos = 0;
+    Ok(status)
+}
+
+/// Response headers as `name: value` lines.
+pub fn headers_text(id: u32, owner: &str) -> Result<String, HttpApiError> {
+    let handle = handle_mut(id, owner)?;
+    let response = handle.response.as_ref().ok_or(HttpApiError::Invalid)?;
+    let mut out = String::new();
+    for (name, value) in response.headers.iter() {
+        out.push_str(name.as_str());
+        out.push_str(": ");
+        out.push_str(value.as_str());
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Copy the next part of the body into `buf`; 0 once the body is exhausted.
+pub fn read(id: u32, owner: &str, buf: &mut [u8]) -> Result<usize, HttpApiError> {
+    let handle = handle_mut(id, owner)?;
+    let response = handle.response.as_ref().ok_or(HttpApiError::Invalid)?;
+    let remaining = response.body.get(handle.read_pos..).unwrap_or(&[]);
+    let n = remaining.len().min(buf.len());
+    buf[..n].copy_from_slice(&remaining[..n]);
+    handle.read_pos += n;
+    Ok(n)
+}
+
+pub fn close(id: u32, owner: &str) -> Result<(), HttpApiError> {
+    // SAFETY: kernel thread, and no handle borrow is alive here.
+    let handles = unsafe { HTTP_HANDLES.get_mut() };
+    let before = handles.len();
+    handles.retain(|h| !(h.id == id && h.owner == owner));
+    if handles.len() == before {
+        return Err(HttpApiError::BadHandle);
+    }
+    Ok(())
+}
+
+/// Drop every handle left open by `owner` (thread exit).
+pub fn close_all(owner: &str) {
+    // SAFETY: kernel thread, and no handle borrow is alive here.
+    unsafe { HTTP_HANDLES.get_mut() }.retain(|h| h.owner != owner);
+}
+
+fn fetch_on_handle(id: u32, owner: &str, headers: &str, body: &[u8]) -> Result<(u16, Vec<u8>), HttpApiError> {
+    for line in headers.lines().filter(|l| !l.trim().is_empty()) {
+        let (name, value) = line.split_once(':').ok_or(HttpApiError::Invalid)?;
+        set_header(id, owner, name, value)?;
+    }
+    let status = send(id, owner, body)?;
+    let handle = handle_mut(id, owner)?;
+    let body = handle.response.take().map(|r| r.body).unwrap_or_default();
+    Ok((status, body))
+}
+
+/// One-shot request for ReduxLang: `headers` holds `Name: value` lines.
+pub fn fetch(
+    owner: &str,
+    method: &str,
+    url: &str,
+    headers: &str,
+    body: &[u8],
+) -> Result<(u16, Vec<u8>), HttpApiError> {
+    let _t = crate::trace::scope_with("net", "http_fetch", url);
+    let id = open(owner, method, url)?;
+    let result = fetch_on_handle(id, owner, headers, body);
+    let _ = close(id, owner);
+    result
+}
+
+crate::selftest::kernel_tests! {
+    "redux_http";
+
+    fn socket_options_apply_and_validate() {
+        let mut options = TcpOptions::from_settings(0, false);
+        let mut timeout = REDUX_HTTP_TIMEOUT_MS;
+        let mut redirects = 0usize;
+        apply_option(&mut options, &mut timeout, &mut redirects, HttpOption::KeepAliveMs, 30_000).map_err(|e| String::from(e.as_str()))?;
+        apply_option(&mut options, &mut timeout, &mut redirects, HttpOption::NoDelay, 1).map_err(|e| String::from(e.as_str()))?;
+        apply_option(&mut options, &mut timeout, &mut redirects, HttpOption::TimeoutMs, 120_000).map_err(|e| String::from(e.as_str()))?;
+        crate::selftest::ensure_eq(
+            options,
+            TcpOptions { keep_alive_ms: Some(30_000), nodelay: true, user_timeout_ms: Some(120_000) },
+            "opciones",
+        )?;
+        crate::selftest::ensure_eq(timeout, 120_000, "timeout en ms")?;
+        crate::selftest::ensure(apply_option(&mut options, &mut timeout, &mut redirects, HttpOption::NoDelay, 2).is_err(), "nodelay invalido")?;
+        crate::selftest::ensure(
+            apply_option(&mut options, &mut timeout, &mut redirects, HttpOption::KeepAliveMs, REDUX_HTTP_MAX_OPTION_MS + 1).is_err(),
+            "intervalo demasiado largo",
+        )?;
+        apply_option(&mut options, &mut timeout, &mut redirects, HttpOption::TimeoutMs, 0).map_err(|e| String::from(e.as_str()))?;
+        crate::selftest::ensure_eq((options.user_timeout_ms, timeout), (None, REDUX_HTTP_TIMEOUT_MS), "timeout por defecto")?;
+        apply_option(&mut options, &mut timeout, &mut redirects, HttpOption::Redirects, 5).map_err(|e| String::from(e.as_str()))?;
+        crate::selftest::ensure_eq(redirects, 5, "redirecciones")?;
+        crate::selftest::ensure(
+            apply_option(&mut options, &mut timeout, &mut redirects, HttpOption::Redirects, REDUX_HTTP_MAX_REDIRECTS + 1).is_err(),
+            "demasiadas redirecciones",
+        )?;
+        crate::selftest::ensure_eq(HttpOption::from_raw(5), None, "opcion desconocida")
+    }
+
+    fn methods_parse_for_the_api() {
+        crate::selftest::ensure_eq(parse_method(" post "), Ok(HttpMethod::Post), "post")?;
+        crate::selftest::ensure_eq(parse_method("DELETE"), Ok(HttpMethod::Delete), "delete")?;
+        crate::selftest::ensure_eq(parse_method("TRACE"), Err(HttpApiError::Unsupported), "trace")?;
+        crate::selftest::ensure_eq(parse_method("G E T"), Err(HttpApiError::Invalid), "invalido")
+    }
+}
@@ -0,0 +1,911 @@
+//! Remote view of the desktop, for headless machines and CI.
+//!
+//! Two listeners serve whatever `framebuffer::capture` returns, so they work
+//! the same on a GOP screen and on the RAM framebuffer used when there is no
+//! display (`display.headless`):
+//!
+//! - a VNC server (RFB 3.3/3.7/3.8, no authentication, raw encoding plus the
+//!   DesktopSize pseudo-encoding) on `remote.vnc_port`. Key and pointer
+//!   events go into the virtio-input queues, so a VNC client drives the
+//!   desktop like a virtio keyboard and tablet would;
+//! - an HTTP endpoint on `remote.http_port` answering `GET /screenshot`
+//!   with the screen as a BMP, for `curl` in CI jobs.
+//!
+//! `remote.vnc` and `remote.http` are `auto` (on only while headless), `on`
+//! or `off`. Neither asks for a password: only turn them on for a screen on
+//! a trusted network. Each serves one client at a time from `net::poll`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use smoltcp::iface::{SocketHandle, SocketSet};
+use smoltcp::socket::tcp;
+
+use crate::config::ConfigValue;
+use crate::framebuffer;
+use crate::sync::KernelCell;
+
+pub const VNC_KEY: &str = "remote.vnc";
+pub const VNC_PORT_KEY: &str = "remote.vnc_port";
+pub const HTTP_KEY: &str = "remote.http";
+pub const HTTP_PORT_KEY: &str = "remote.http_port";
+const VNC_PORT_DEFAULT: i64 = 5900;
+const HTTP_PORT_DEFAULT: i64 = 8080;
+/// Settings are re-read once a second, not on every poll.
+const CONFIG_REFRESH_TICKS: u64 = 100;
+/// At most one screen capture per 50 ms for VNC updates.
+const VNC_FRAME_TICKS: u64 = 5;
+const VNC_DESKTOP_NAME: &str = "ReduxOS";
+const ENCODING_RAW: i32 = 0;
+const ENCODING_DESKTOP_SIZE: i32 = -223;
+const RX_BUFFER_BYTES: usize = 4096;
+const TX_BUFFER_BYTES: usize = 64 * 1024;
+/// Longest HTTP request head accepted.
+const HTTP_REQUEST_MAX: usize = 4096;
+/// Idle HTTP connections are dropped after 10 s.
+const HTTP_IDLE_TICKS: u64 = 1_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Auto,
+    On,
+    Off,
+}
+
+impl Mode {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim() {
+            "auto" => Some(Mode::Auto),
+            "on" => Some(Mode::On),
+            "off" => Some(Mode::Off),
+            _ => None,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Mode::Auto => "auto",
+            Mode::On => "on",
+            Mode::Off => "off",
+        }
+    }
+
+    fn enabled(self) -> bool {
+        match self {
+            Mode::Auto => framebuffer::is_headless(),
+            Mode::On => true,
+            Mode::Off => false,
+        }
+    }
+}
+
+fn mode(key: &str) -> Mode {
+    Mode::parse(crate::config::get_str(key, "auto").as_str()).unwrap_or(Mode::Auto)
+}
+
+fn port(key: &str, default: i64) -> u16 {
+    crate::config::get_int(key, default).clamp(1, u16::MAX as i64) as u16
+}
+
+// ─── Pixel formats ───────────────────────────────────────────────────────────
+
+/// RFB PIXEL_FORMAT for true-colour pixels.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PixelFormat {
+    pub bits_per_pixel: u8,
+    pub big_endian: bool,
+    pub max: [u16; 3],
+    pub shift: [u8; 3],
+}
+
+/// What the server offers: 0x00RRGGBB little endian, like `capture`.
+pub const NATIVE_FORMAT: PixelFormat = PixelFormat {
+    bits_per_pixel: 32,
+    big_endian: false,
+    max: [255, 255, 255],
+    shift: [16, 8, 0],
+};
+
+impl PixelFormat {
+    fn to_wire(self) -> [u8; 16] {
+        let mut out = [0u8; 16];
+        out[0] = self.bits_per_pixel;
+        out[1] = if self.bits_per_pixel == 32 {
+            24
+        } else {
+            self.bits_per_pixel
+        };
+        out[2] = self.big_endian as u8;
+        out[3] = 1;
+        out[4..6].copy_from_slice(&self.max[0].to_be_bytes());
+        out[6..8].copy_from_slice(&self.max[1].to_be_bytes());
+        out[8..10].copy_from_slice(&self.max[2].to_be_bytes());
+        out[10..13].copy_from_slice(&self.shift);
+        out
+    }
+
+    /// `None` for colour-map formats and odd pixel sizes.
+    fn from_wire(wire: &[u8]) -> Option<Self> {
+        let bits_per_pixel = wire[0];
+        if wire[3] == 0 || !matches!(bits_per_pixel, 8 | 16 | 32) {
+            return None;
+        }
+        let max = |i: usize| u16::from_be_bytes([wire[i], wire[i + 1]]);
+        Some(Self {
+            bits_per_pixel,
+            big_endian: wire[2] != 0,
+            max: [max(4), max(6), max(8)],
+            shift: [wire[10], wire[11], wire[12]],
+        })
+    }
+
+    /// Append one 0xRRGGBB pixel in this format.
+    pub fn push_pixel(&self, rgb: u32, out: &mut Vec<u8>) {
+        let channels = [(rgb >> 16) & 0xFF, (rgb >> 8) & 0xFF, rgb & 0xFF];
+        let mut value = 0u32;
+        for ((channel, max), shift) in channels.iter().zip(self.max).zip(self.shift) {
+            value |= (channel * max as u32 / 255).checked_shl(shift as u32).unwrap_or(0);
+        }
+        match (self.bits_per_pixel, self.big_endian) {
+            (8, _) => out.push(value as u8),
+            (16, false) => out.extend_from_slice(&(value as u16).to_le_bytes()),
+            (16, true) => out.extend_from_slice(&(value as u16).to_be_bytes()),
+            (_, false) => out.extend_from_slice(&value.to_le_bytes()),
+            (_, true) => out.extend_from_slice(&value.to_be_bytes()),
+        }
+    }
+}
+
+// ─── RFB messages ────────────────────────────────────────────────────────────
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ClientMessage {
+    SetPixelFormat(Option<PixelFormat>),
+    SetEncodings(Vec<i32>),
+    UpdateRequest { incremental: bool },
+    Key { down: bool, keysym: u32 },
+    Pointer { buttons: u8, x: u16, y: u16 },
+    CutText,
+}
+
+/// Parse one client-to-server message from the front of `buf`; returns it
+/// with its length, `Ok(None)` if more bytes are needed.
+pub fn parse_client_message(buf: &[u8]) -> Result<Option<(ClientMessage, usize)>, &'static str> {
+    let Some(&kind) = buf.first() else {
+        return Ok(None);
+    };
+    let need = match kind {
+        0 => 20,
+        2 if buf.len() >= 4 => 4 + 4 * u16::from_be_bytes([buf[2], buf[3]]) as usize,
+        3 => 10,
+        4 => 8,
+        5 => 6,
+        6 if buf.len() >= 8 => 8 + u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]) as usize,
+        2 | 6 => return Ok(None),
+        _ => return Err("mensaje VNC desconocido"),
+    };
+    if buf.len() < need {
+        return Ok(None);
+    }
+    let message = match kind {
+        0 => ClientMessage::SetPixelFormat(PixelFormat::from_wire(&buf[4..20])),
+        2 => ClientMessage::SetEncodings(
+            buf[4..need]
+                .chunks_exact(4)
+                .map(|c| i32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+                .collect(),
+        ),
+        3 => ClientMessage::UpdateRequest {
+            incremental: buf[1] != 0,
+        },
+        4 => ClientMessage::Key {
+            down: buf[1] != 0,
+            keysym: u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]),
+        },
+        5 => ClientMessage::Pointer {
+            buttons: buf[1],
+            x: u16::from_be_bytes([buf[2], buf[3]]),
+            y: u16::from_be_bytes([buf[4], buf[5]]),
+        },
+        _ => ClientMessage::CutText,
+    };
+    Ok(Some((message, need)))
+}
+
+/// Linux evdev code for an X keysym, US layout. Shifted symbols map to their
+/// base key: the client sends Shift on its own.
+pub fn keysym_to_evdev(keysym: u32) -> Option<u16> {
+    const ROW_NUMBERS: &[u8] = b"1234567890-=";
+    const ROW_TOP: &[u8] = b"qwertyuiop[]";
+    const ROW_HOME: &[u8] = b"asdfghjkl;'`";
+    const ROW_BOTTOM: &[u8] = b"zxcvbnm,./";
+    const SHIFTED: &[(u8, u8)] = &[
+        (b'!', b'1'),
+        (b'@', b'2'),
+        (b'#', b'3'),
+        (b'$', b'4'),
+        (b'%', b'5'),
+        (b'^', b'6'),
+        (b'&', b'7'),
+        (b'*', b'8'),
+        (b'(', b'9'),
+        (b')', b'0'),
+        (b'_', b'-'),
+        (b'+', b'='),
+        (b'{', b'['),
+        (b'}', b']'),
+        (b':', b';'),
+        (b'"', b'\''),
+        (b'~', b'`'),
+        (b'|', b'\\'),
+        (b'<', b','),
+        (b'>', b'.'),
+        (b'?', b'/'),
+    ];
+    let code = match keysym {
+        0xFF08 => 14,
+        0xFF09 => 15,
+        0xFF0D => 28,
+        0xFF8D => 96,
+        0xFF1B => 1,
+        0xFF51 => 105,
+        0xFF52 => 103,
+        0xFF53 => 106,
+        0xFF54 => 108,
+        0xFF55 => 104,
+        0xFF56 => 109,
+        0xFFBE => 59,
+        0xFFBF => 60,
+        0xFFC9 => 88,
+        0xFFE1 => 42,
+        0xFFE2 => 54,
+        0xFFEB => 125,
+        0xFFEC => 126,
+        0x20 => 57,
+        0x21..=0x7E => {
+            let mut ch = (keysym as u8).to_ascii_lowercase();
+            if let Some((_, base)) = SHIFTED.iter().find(|(shifted, _)| *shifted == ch) {
+                ch = *base;
+            }
+            let find = |row: &[u8], first: u16| row.iter().position(|c| *c == ch).map(|i| first + i as u16);
+            find(ROW_NUMBERS, 2)
+                .or_else(|| find(ROW_TOP, 16))
+                .or_else(|| find(ROW_HOME, 30))
+                .or_else(|| find(ROW_BOTTOM, 44))
+                .or((ch == b'\\').then_some(43))?
+        }
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Smallest rectangle (x, y, w, h) covering every pixel that differs.
+pub fn dirty_rect(old: &[u32], new: &[u32], width: usize) -> Option<(usize, usize, usize, usize)> {
+    if width == 0 || old.len() != new.len() {
+        return None;
+    }
+    let (mut x0, mut y0, mut x1, mut y1) = (usize::MAX, usize::MAX, 0, 0);
+    for (y, (old_row, new_row)) in old.chunks_exact(width).zip(new.chunks_exact(width)).enumerate() {
+        if old_row == new_row {
+            continue;
+        }
+        let first = old_row.iter().zip(new_row).position(|(a, b)| a != b).unwrap_or(0);
+        let last = old_row.iter().zip(new_row).rposition(|(a, b)| a != b).unwrap_or(0);
+        x0 = x0.min(first);
+        x1 = x1.max(last);
+        y0 = y0.min(y);
+        y1 = y;
+    }
+    if y0 == usize::MAX {
+        return None;
+    }
+    Some((x0, y0, x1 - x0 + 1, y1 - y0 + 1))
+}
+
+fn server_init(width: usize, height: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&(width as u16).to_be_bytes());
+    out.extend_from_slice(&(height as u16).to_be_bytes());
+    out.extend_from_slice(&NATIVE_FORMAT.to_wire());
+    out.extend_from_slice(&(VNC_DESKTOP_NAME.len() as u32).to_be_bytes());
+    out.extend_from_slice(VNC_DESKTOP_NAME.as_bytes());
+    out
+}
+
+fn push_rect_header(out: &mut Vec<u8>, rect: (usize, usize, usize, usize), encoding: i32) {
+    for value in [rect.0, rect.1, rect.2, rect.3] {
+        out.extend_from_slice(&(value as u16).to_be_bytes());
+    }
+    out.extend_from_slice(&encoding.to_be_bytes());
+}
+
+// ─── Connections ─────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Version,
+    SecurityChoice,
+    ClientInit,
+    Ready,
+}
+
+struct Listener {
+    handle: Option<SocketHandle>,
+    port: u16,
+    connected: bool,
+    inbox: Vec<u8>,
+    outbox: Vec<u8>,
+    sent: usize,
+    since_tick: u64,
+    clients: u64,
+}
+
+impl Listener {
+    const fn new() -> Self {
+        Self {
+            handle: None,
+            port: 0,
+            connected: false,
+            inbox: Vec::new(),
+            outbox: Vec::new(),
+            sent: 0,
+            since_tick: 0,
+            clients: 0,
+        }
+    }
+
+    fn release(&mut self, sockets: &mut SocketSet<'_>) {
+        if let Some(handle) = self.handle.take() {
+            sockets.get_mut::<tcp::Socket>(handle).abort();
+            sockets.remove(handle);
+        }
+        self.connected = false;
+    }
+
+    /// Keep a socket listening on `port`; returns it once a client is
+    /// connected, with `true` the first time this client is seen.
+    fn accept<'a>(
+        &mut self,
+        sockets: &'a mut SocketSet<'static>,
+        port: u16,
+        now_ticks: u64,
+    ) -> Option<(&'a mut tcp::Socket<'static>, bool)> {
+        if self.handle.is_some() && self.port != port {
+            self.release(sockets);
+        }
+        let handle = match self.handle {
+            Some(handle) => handle,
+            None => {
+                let socket = tcp::Socket::new(
+                    tcp::SocketBuffer::new(alloc::vec![0u8; RX_BUFFER_BYTES]),
+                    tcp::SocketBuffer::new(alloc::vec![0u8; TX_BUFFER_BYTES]),
+                );
+                let handle = sockets.add(socket);
+                self.handle = Some(handle);
+                self.port = port;
+                handle
+            }
+        };
+        let socket = sockets.get_mut::<tcp::Socket>(handle);
+        // Skip TIME-WAIT so the next client can connect right away.
+        if socket.state() == tcp::State::TimeWait {
+            socket.abort();
+        }
+        if !socket.is_open() {
+            self.connected = false;
+            if socket.listen(port).is_err() {
+                return None;
+            }
+        }
+        if !socket.is_active() {
+            return None;
+        }
+        let fresh = !self.connected;
+        if fresh {
+            self.connected = true;
+            self.inbox.clear();
+            self.outbox.clear();
+            self.sent = 0;
+            self.since_tick = now_ticks;
+            self.clients = self.clients.saturating_add(1);
+        }
+        Some((socket, fresh))
+    }
+
+    fn receive(&mut self, socket: &mut tcp::Socket<'_>, limit: usize) {
+        let mut chunk = [0u8; 512];
+        while socket.can_recv() && self.inbox.len() < limit {
+            match socket.recv_slice(&mut chunk) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => self.inbox.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+
+    /// Push queued output; true once all of it is in the socket.
+    fn flush(&mut self, socket: &mut tcp::Socket<'_>) -> bool {
+        while self.sent < self.outbox.len() && socket.can_send() {
+            match socket.send_slice(&self.outbox[self.sent..]) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => self.sent += n,
+            }
+        }
+        if self.sent >= self.outbox.len() {
+            self.outbox.clear();
+            self.sent = 0;
+            return true;
+        }
+        false
+    }
+}
+
+struct VncSession {
+    phase: Phase,
+    /// Minor protocol version the client asked for (3, 7 or 8).
+    minor: u8,
+    format: PixelFormat,
+    desktop_size: bool,
+    /// A FramebufferUpdateRequest waiting for something to send.
+    pending: Option<bool>,
+    last_frame: Vec<u32>,
+    width: usize,
+    height: usize,
+    last_capture_tick: u64,
+    buttons: u8,
+}
+
+impl VncSession {
+    const fn new() -> Self {
+        Self {
+            phase: Phase::Version,
+            minor: 8,
+            format: NATIVE_FORMAT,
+            desktop_size: false,
+            pending: None,
+            last_frame: Vec::new(),
+            width: 0,
+            height: 0,
+            last_capture_tick: 0,
+            buttons: 0,
+        }
+    }
+}
+
+struct RemoteState {
+    vnc_mode: Mode,
+    http_mode: Mode,
+    vnc_port: u16,
+    http_port: u16,
+    config_tick: Option<u64>,
+    vnc: Listener,
+    session: VncSession,
+    http: Listener,
+    screenshots: u64,
+}
+
+static REMOTE: KernelCell<RemoteState> = KernelCell::new(RemoteState {
+    vnc_mode: Mode::Auto,
+    http_mode: Mode::Auto,
+    vnc_port: VNC_PORT_DEFAULT as u16,
+    http_port: HTTP_PORT_DEFAULT as u16,
+    config_tick: None,
+    vnc: Listener::new(),
+    session: VncSession::new(),
+    http: Listener::new(),
+    screenshots: 0,
+});
+
+/// Called from `net::poll`.
+pub fn pump(sockets: &mut SocketSet<'static>, now_ticks: u64) {
+    // SAFETY: `net::poll` and the shell both run on the kernel thread.
+    let state = unsafe { REMOTE.get_mut() };
+    if state
+        .config_tick
+        .is_none_or(|tick| now_ticks.saturating_sub(tick) >= CONFIG_REFRESH_TICKS)
+    {
+        state.config_tick = Some(now_ticks);
+        state.vnc_mode = mode(VNC_KEY);
+        state.http_mode = mode(HTTP_KEY);
+        state.vnc_port = port(VNC_PORT_KEY, VNC_PORT_DEFAULT);
+        state.http_port = port(HTTP_PORT_KEY, HTTP_PORT_DEFAULT);
+    }
+
+    if state.vnc_mode.enabled() {
+        pump_vnc(state, sockets, now_ticks);
+    } else {
+        state.vnc.release(sockets);
+    }
+    if state.http_mode.enabled() {
+        pump_http(state, sockets, now_ticks);
+    } else {
+        state.http.release(sockets);
+    }
+}
+
+fn pump_vnc(state: &mut RemoteState, sockets: &mut SocketSet<'static>, now_ticks: u64) {
+    let Some((socket, fresh)) = state.vnc.accept(sockets, state.vnc_port, now_ticks) else {
+        return;
+    };
+    let listener = &mut state.vnc;
+    let session = &mut state.session;
+    if fresh {
+        *session = VncSession::new();
+        listener.outbox.extend_from_slice(b"RFB 003.008\n");
+    }
+    listener.receive(socket, RX_BUFFER_BYTES * 4);
+    if !socket.may_recv() {
+        socket.close();
+        return;
+    }
+
+    loop {
+        let consumed = match session.phase {
+            Phase::Version => {
+                if listener.inbox.len() < 12 {
+                    break;
+                }
+                let minor = core::str::from_utf8(&listener.inbox[8..11])
+                    .ok()
+                    .and_then(|digits| digits.parse::<u16>().ok())
+                    .unwrap_or(3);
+                session.minor = match minor {
+                    0..=6 => 3,
+                    7 => 7,
+                    _ => 8,
+                };
+                if session.minor == 3 {
+                    // 3.3: the server picks the security type, None.
+                    listener.outbox.extend_from_slice(&1u32.to_be_bytes());
+                    session.phase = Phase::ClientInit;
+                } else {
+                    listener.outbox.extend_from_slice(&[1, 1]);
+                    session.phase = Phase::SecurityChoice;
+                }
+                12
+            }
+            Phase::SecurityChoice => {
+                let Some(&choice) = listener.inbox.first() else {
+                    break;
+                };
+                if choice != 1 {
+                    socket.abort();
+                    return;
+                }
+                if session.minor >= 8 {
+                    listener.outbox.extend_from_slice(&0u32.to_be_bytes());
+                }
+                session.phase = Phase::ClientInit;
+                1
+            }
+            Phase::ClientInit => {
+                if listener.inbox.is_empty() {
+                    break;
+                }
+                let (width, height) = framebuffer::dimensions();
+                session.width = width;
+                session.height = height;
+                listener.outbox.extend_from_slice(&server_init(width, height));
+                session.phase = Phase::Ready;
+                1
+            }
+            Phase::Ready => match parse_client_message(listener.inbox.as_slice()) {
+                // Nothing legitimate is this long (cut text at most).
+                Ok(None) if listener.inbox.len() >= RX_BUFFER_BYTES * 4 => {
+                    socket.abort();
+                    return;
+                }
+                Ok(None) => break,
+                Err(_) => {
+                    socket.abort();
+                    return;
+                }
+                Ok(Some((message, len))) => {
+                    handle_client_message(session, message);
+                    len
+                }
+            },
+        };
+        listener.inbox.drain(..consumed);
+    }
+
+    if !listener.flush(socket) {
+        return;
+    }
+    if session.pending.is_some() && now_ticks.saturating_sub(session.last_capture_tick) >= VNC_FRAME_TICKS {
+        session.last_capture_tick = now_ticks;
+        if !queue_update(session, &mut listener.outbox) {
+            // Resized under a client that can't follow: let it reconnect.
+            socket.close();
+            return;
+        }
+        listener.flush(socket);
+    }
+}
+
+fn handle_client_message(session: &mut VncSession, message: ClientMessage) {
+    match message {
+        ClientMessage::SetPixelFormat(format) => {
+            session.format = format.unwrap_or(NATIVE_FORMAT);
+            session.last_frame.clear();
+        }
+        ClientMessage::SetEncodings(encodings) => {
+            session.desktop_size = encodings.contains(&ENCODING_DESKTOP_SIZE);
+        }
+        ClientMessage::UpdateRequest { incremental } => {
+            let incremental = incremental && session.pending != Some(false);
+            session.pending = Some(incremental);
+        }
+        ClientMessage::Key { down, keysym } => {
+            if let Some(code) = keysym_to_evdev(keysym) {
+                crate::input::push_virtio_key(code, down as u32);
+            }
+        }
+        ClientMessage::Pointer { buttons, x, y } => {
+            // Buttons 4 and 5 are the wheel; count presses only.
+            let pressed = buttons & !session.buttons;
+            let wheel = if pressed & 0x08 != 0 {
+                1
+            } else if pressed & 0x10 != 0 {
+                -1
+            } else {
+                0
+            };
+            session.buttons = buttons;
+            crate::input::push_virtio_pointer(crate::input::VirtioPointerReport {
+                dx: 0,
+                dy: 0,
+                wheel,
+                left: buttons & 0x01 != 0,
+                right: buttons & 0x04 != 0,
+                absolute: Some((x as i32, y as i32)),
+            });
+        }
+        ClientMessage::CutText => {}
+    }
+}
+
+/// Queue a FramebufferUpdate if the screen changed (or a full one was
+/// asked for). False when the screen was resized and the client has no
+/// DesktopSize support.
+fn queue_update(session: &mut VncSession, out: &mut Vec<u8>) -> bool {
+    let Some(incremental) = session.pending else {
+        return true;
+    };
+    let (width, height) = framebuffer::dimensions();
+    let frame = framebuffer::capture();
+    if width == 0 || height == 0 || frame.len() < width * height {
+        return true;
+    }
+    let resized = (width, height) != (session.width, session.height);
+    if resized && !session.desktop_size {
+        return false;
+    }
+    let rect = if incremental && !resized && session.last_frame.len() == frame.len() {
+        match dirty_rect(session.last_frame.as_slice(), frame.as_slice(), width) {
+            Some(rect) => rect,
+            // Nothing new; the request stays pending.
+            None => return true,
+        }
+    } else {
+        (0, 0, width, height)
+    };
+
+    out.extend_from_slice(&[0, 0]);
+    out.extend_from_slice(&(if resized { 2u16 } else { 1u16 }).to_be_bytes());
+    if resized {
+        push_rect_header(out, (0, 0, width, height), ENCODING_DESKTOP_SIZE);
+        session.width = width;
+        session.height = height;
+    }
+    push_rect_header(out, rect, ENCODING_RAW);
+    let (x, y, w, h) = rect;
+    out.reserve(w * h * (session.format.bits_per_pixel as usize / 8));
+    for row in frame[y * width..(y + h) * width].chunks_exact(width) {
+        for px in &row[x..x + w] {
+            session.format.push_pixel(*px, out);
+        }
+    }
+    session.last_frame = frame;
+    session.pending = None;
+    true
+}
+
+/// Path of a complete `GET` request head; `Err` with a status line for
+/// anything else.
+pub fn http_request_path(head: &[u8]) -> Result<&str, &'static str> {
+    let line = head.split(|b| *b == b'\n').next().unwrap_or(&[]);
+    let line = core::str::from_utf8(line).map_err(|_| "400 Bad Request")?.trim_end();
+    let mut parts = line.split(' ');
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    if !path.starts_with('/') {
+        return Err("400 Bad Request");
+    }
+    if method != "GET" {
+        return Err("405 Method Not Allowed");
+    }
+    Ok(path.split('?').next().unwrap_or(path))
+}
+
+fn http_response(status: &str, content_type: &str, body: &[u8]) -> Vec<u8> {
+    let mut out = alloc::format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )
+    .into_bytes();
+    out.extend_from_slice(body);
+    out
+}
+
+fn pump_http(state: &mut RemoteState, sockets: &mut SocketSet<'static>, now_ticks: u64) {
+    let Some((socket, _)) = state.http.accept(sockets, state.http_port, now_ticks) else {
+        return;
+    };
+    let listener = &mut state.http;
+    if listener.outbox.is_empty() {
+        listener.receive(socket, HTTP_REQUEST_MAX);
+        let complete = listener.inbox.windows(4).any(|w| w == b"\r\n\r\n");
+        if !complete {
+            if listener.inbox.len() >= HTTP_REQUEST_MAX
+                || now_ticks.saturating_sub(listener.since_tick) >= HTTP_IDLE_TICKS
+                || !socket.may_recv()
+            {
+                socket.abort();
+            }
+            return;
+        }
+        let response = match http_request_path(listener.inbox.as_slice()) {
+            Ok("/screenshot") | Ok("/screenshot.bmp") => match crate::gui::screenshot::capture_bmp() {
+                Some(bmp) => {
+                    state.screenshots = state.screenshots.saturating_add(1);
+                    http_response("200 OK", "image/bmp", bmp.as_slice())
+                }
+                None => http_response("503 Service Unavailable", "text/plain", b"sin framebuffer\n"),
+            },
+            Ok("/") => {
+                let (width, height) = framebuffer::dimensions();
+                let body = alloc::format!(
+                    "ReduxOS {}x{} ({})\nGET /screenshot -> BMP\n",
+                    width,
+                    height,
+                    display_label()
+                );
+                http_response("200 OK", "text/plain; charset=utf-8", body.as_bytes())
+            }
+            Ok(_) => http_response("404 Not Found", "text/plain", b"no encontrado\n"),
+            Err(status) => http_response(status, "text/plain", b""),
+        };
+        listener.inbox.clear();
+        listener.outbox = response;
+        listener.sent = 0;
+    }
+    if listener.flush(socket) {
+        socket.close();
+    }
+}
+
+fn display_label() -> &'static str {
+    if framebuffer::is_headless() {
+        "framebuffer en RAM"
+    } else {
+        "GOP"
+    }
+}
+
+fn service_line(name: &str, mode: Mode, port: u16, listener: &Listener, extra: &str) -> String {
+    let status = if !mode.enabled() {
+        String::from("desactivado")
+    } else if listener.connected {
+        alloc::format!("puerto {}, cliente conectado", port)
+    } else if listener.handle.is_some() {
+        alloc::format!("puerto {}, esperando", port)
+    } else {
+        alloc::format!("puerto {}, sin red", port)
+    };
+    alloc::format!(
+        "{}: {} [{}], {} conexiones{}",
+        name,
+        status,
+        mode.name(),
+        listener.clients,
+        extra
+    )
+}
+
+/// Shared implementation of `remote [status|vnc on|off|auto|http on|off|auto]`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args: Vec<&str> = args.split_whitespace().collect();
+    let key = match args.as_slice() {
+        [] | ["status"] => {
+            let state = unsafe { REMOTE.get() };
+            let (width, height) = framebuffer::dimensions();
+            return alloc::vec![
+                alloc::format!("Pantalla: {}x{} ({})", width, height, display_label()),
+                service_line(
+                    "VNC",
+                    mode(VNC_KEY),
+                    port(VNC_PORT_KEY, VNC_PORT_DEFAULT),
+                    &state.vnc,
+                    ""
+                ),
+                service_line(
+                    "HTTP",
+                    mode(HTTP_KEY),
+                    port(HTTP_PORT_KEY, HTTP_PORT_DEFAULT),
+                    &state.http,
+                    alloc::format!(", {} capturas (/screenshot)", state.screenshots).as_str()
+                ),
+            ];
+        }
+        ["vnc", _] => VNC_KEY,
+        ["http", _] => HTTP_KEY,
+        _ => return alloc::vec![String::from("Uso: remote [status|vnc on|off|auto|http on|off|auto]")],
+    };
+    let Some(wanted) = Mode::parse(args[1]) else {
+        return alloc::vec![String::from("Uso: remote [status|vnc on|off|auto|http on|off|auto]")];
+    };
+    match crate::config::set(key, ConfigValue::Str(String::from(wanted.name()))) {
+        Ok(()) => {
+            unsafe { REMOTE.get_mut().config_tick = None };
+            alloc::vec![alloc::format!("{}: {}", key, wanted.name())]
+        }
+        Err(e) => alloc::vec![alloc::format!("config: {}", e)],
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "remote";
+
+    fn client_messages_parse_incrementally() {
+        let request = [3, 1, 0, 0, 0, 0, 4, 0, 3, 0];
+        crate::selftest::ensure_eq(parse_client_message(&request[..6]), Ok(None), "incompleto")?;
+        crate::selftest::ensure_eq(
+            parse_client_message(&request),
+            Ok(Some((ClientMessage::UpdateRequest { incremental: true }, 10))),
+            "peticion",
+        )?;
+        let encodings = [2, 0, 0, 2, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0x21];
+        crate::selftest::ensure_eq(
+            parse_client_message(&encodings),
+            Ok(Some((ClientMessage::SetEncodings(alloc::vec![ENCODING_RAW, ENCODING_DESKTOP_SIZE]), 12))),
+            "codificaciones",
+        )?;
+        crate::selftest::ensure_eq(
+            parse_client_message(&[5, 1, 0, 10, 0, 20]),
+            Ok(Some((ClientMessage::Pointer { buttons: 1, x: 10, y: 20 }, 6))),
+            "puntero",
+        )?;
+        crate::selftest::ensure_eq(parse_client_message(&[9]), Err("mensaje VNC desconocido"), "desconocido")
+    }
+
+    fn pixels_follow_the_client_format() {
+        let mut out = Vec::new();
+        NATIVE_FORMAT.push_pixel(0x123456, &mut out);
+        crate::selftest::ensure_eq(out.as_slice(), [0x56, 0x34, 0x12, 0].as_slice(), "nativo")?;
+        let rgb565 = PixelFormat { bits_per_pixel: 16, big_endian: true, max: [31, 63, 31], shift: [11, 5, 0] };
+        crate::selftest::ensure_eq(PixelFormat::from_wire(&rgb565.to_wire()), Some(rgb565), "ida y vuelta")?;
+        out.clear();
+        rgb565.push_pixel(0xFF0000, &mut out);
+        crate::selftest::ensure_eq(out.as_slice(), [0xF8, 0x00].as_slice(), "rojo 565")
+    }
+
+    fn keys_and_dirty_regions() {
+        crate::selftest::ensure_eq(keysym_to_evdev(b'a' as u32), Some(30), "a")?;
+        crate::selftest::ensure_eq(keysym_to_evdev(b'Q' as u32), Some(16), "Q")?;
+        crate::selftest::ensure_eq(keysym_to_evdev(b'?' as u32), Some(53), "?")?;
+        crate::selftest::ensure_eq(keysym_to_evdev(0xFF0D), Some(28), "intro")?;
+        crate::selftest::ensure_eq(keysym_to_evdev(0x20AC), None, "euro")?;
+        let old = [0u32; 12];
+        let mut new = old;
+        new[5] = 1;
+        new[10] = 1;
+        crate::selftest::ensure_eq(dirty_rect(&old, &new, 4), Some((1, 1, 2, 2)), "rectangulo")?;
+        crate::selftest::ensure_eq(dirty_rect(&old, &old, 4), None, "sin cambios")
+    }
+
+    fn http_requests_route_by_path() {
+        crate::selftest::ensure_eq(http_request_path(b"GET /screenshot?t=1 HTTP/1.1\r\nHost: x\r\n\r\n"), Ok("/screenshot"), "get")?;
+        crate::selftest::ensure_eq(http_request_path(b"POST / HTTP/1.1\r\n\r\n"), Err("405 Method Not Allowed"), "post")?;
+        crate::selftest::ensure_eq(http_request_path(b"hola\r\n\r\n"), Err("400 Bad Request"), "basura")
+    }
+}
@@ -0,0 +1,282 @@
+//! HTTP requests with any method, headers and body.
+//!
+//! `HttpRequest` is a small builder over the blocking client in `net`:
+//!
+//! - GET requests keep the cache, the HTTPS compatibility proxy, HTTP/2 and
+//!   the keep-alive pool.
+//! - Other methods skip the cache and the proxy, and over HTTPS they use
+//!   HTTP/1.1 only.
+//! - Non-idempotent methods (POST, PATCH) never go out on a pooled socket.
+//!   They are retried only when the request never reached the socket.
+//! - Bodies of `EXPECT_CONTINUE_MIN_BYTES` or more are sent with
+//!   `Expect: 100-continue`, so a server that refuses them (401, 413, ...)
+//!   does not make us push the whole body first.
+//! - Redirects are followed only when asked for (`RedirectPolicy`), with the
+//!   method rewrites of RFC 9110 15.4.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub use redux_netparse::http::HttpMethod;
+use redux_netparse::http::{header_first, parse_http_headers, redirect_method, request_retry_allowed};
+use redux_netparse::starts_with_ignore_ascii_case;
+use redux_netparse::url::{extract_url_host, resolve_reference};
+
+use super::TcpOptions;
+use crate::println;
+
+/// Bodies at least this large ask for `100 Continue` first.
+pub const EXPECT_CONTINUE_MIN_BYTES: usize = 64 * 1024;
+/// How long to wait for `100 Continue` before sending the body anyway.
+pub const EXPECT_CONTINUE_WAIT_MS: u64 = 1_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RedirectPolicy {
+    /// Return 3xx responses to the caller.
+    None,
+    /// Follow up to this many redirects.
+    Follow(usize),
+    /// Follow up to this many redirects, but only to `https://` URLs.
+    FollowHttps(usize),
+}
+
+#[derive(Clone)]
+pub struct HttpRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub redirects: RedirectPolicy,
+    /// Idle timeout for each network step, on the `timer::now_ms` clock.
+    pub timeout_ms: u64,
+    pub options: TcpOptions,
+}
+
+impl HttpRequest {
+    pub fn new(method: HttpMethod, url: &str) -> Self {
+        Self {
+            method,
+            url: String::from(url),
+            headers: Vec::new(),
+            body: Vec::new(),
+            redirects: RedirectPolicy::None,
+            timeout_ms: super::NET_BLOCKING_TIMEOUT_MS,
+            options: TcpOptions::defaults(),
+        }
+    }
+
+    pub fn get(url: &str) -> Self {
+        Self::new(HttpMethod::Get, url)
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((String::from(name), String::from(value)));
+        self
+    }
+
+    /// Attach a body; `content_type` is sent as `Content-Type` when not empty.
+    pub fn body(mut self, content_type: &str, body: Vec<u8>) -> Self {
+        if !content_type.is_empty() {
+            self.headers
+                .retain(|(name, _)| !name.eq_ignore_ascii_case("content-type"));
+            self.headers
+                .push((String::from("Content-Type"), String::from(content_type)));
+        }
+        self.body = body;
+        self
+    }
+
+    pub fn redirects(mut self, policy: RedirectPolicy) -> Self {
+        self.redirects = policy;
+        self
+    }
+
+    pub fn timeout_ms(mut self, ms: u64) -> Self {
+        self.timeout_ms = ms;
+        self
+    }
+
+    pub fn options(mut self, options: TcpOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn expects_continue(&self) -> bool {
+        self.body.len() >= EXPECT_CONTINUE_MIN_BYTES
+    }
+
+    /// The request to send after a `status` redirect to `location`, or
+    /// `None` if the status is not a redirect. Bodies are dropped when the
+    /// method turns into GET; credentials are dropped across hosts.
+    pub fn redirected(&self, status: u16, location: &str) -> Option<Self> {
+        let (method, keep_body) = redirect_method(self.method, status)?;
+        let url = resolve_reference(self.url.as_str(), location);
+        let same_host = match (extract_url_host(self.url.as_str()), extract_url_host(url.as_str())) {
+            (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+            _ => false,
+        };
+        let mut next = self.clone();
+        next.method = method;
+        next.url = url;
+        if !keep_body {
+            next.body.clear();
+            next.headers
+                .retain(|(name, _)| !name.eq_ignore_ascii_case("content-type"));
+        }
+        if !same_host {
+            next.headers.retain(|(name, _)| {
+                !name.eq_ignore_ascii_case("authorization") && !name.eq_ignore_ascii_case("cookie")
+            });
+        }
+        Some(next)
+    }
+
+    /// Send the request, retrying and following redirects as configured.
+    /// Returns the raw response (head and decoded body).
+    pub fn send(&self, pump_ui: &mut impl FnMut()) -> Option<Vec<u8>> {
+        let _t = crate::trace::scope_with("net", "http_request", self.url.as_str());
+        let key = self.replay_key();
+        if let Some(reply) = crate::replay::network_reply(key.as_str()) {
+            return reply;
+        }
+        let response = self.send_live(pump_ui);
+        crate::replay::record_reply(key.as_str(), response.as_deref());
+        response
+    }
+
+    /// What `crate::replay` files the reply to this request under.
+    pub fn replay_key(&self) -> String {
+        alloc::format!("{} {}", self.method.as_str(), self.url)
+    }
+
+    fn send_live(&self, pump_ui: &mut impl FnMut()) -> Option<Vec<u8>> {
+        let mut current = self.clone();
+        let mut hops = 0usize;
+        loop {
+            let response = current.send_with_retries(pump_ui)?;
+            match current.next_hop(response.as_slice(), hops) {
+                Some(next) => current = next,
+                None => return Some(response),
+            }
+            hops += 1;
+        }
+    }
+
+    fn send_with_retries(&self, pump_ui: &mut impl FnMut()) -> Option<Vec<u8>> {
+        let mut attempt = 0usize;
+        loop {
+            let mut sent = false;
+            let response = super::http_request_once(self, pump_ui, &mut sent);
+            match self.retry_after(attempt, response.as_deref(), sent) {
+                Some(backoff) => super::http_wait_ms_with_ui(pump_ui, backoff),
+                None => return response,
+            }
+            attempt += 1;
+        }
+    }
+
+    /// The request to send next when `response` is a redirect the policy
+    /// follows, `hops` redirects in.
+    pub fn next_hop(&self, response: &[u8], hops: usize) -> Option<Self> {
+        let (limit, https_only) = match self.redirects {
+            RedirectPolicy::None => (0, false),
+            RedirectPolicy::Follow(n) => (n, false),
+            RedirectPolicy::FollowHttps(n) => (n, true),
+        };
+        if hops >= limit {
+            return None;
+        }
+        let parsed = parse_http_headers(response);
+        let status = parsed.status_code?;
+        let location = header_first(parsed.headers.as_slice(), "location")?;
+        let next = self.redirected(status, location)?;
+        if https_only && !starts_with_ignore_ascii_case(next.url.as_str(), "https://") {
+            return None;
+        }
+        println(alloc::format!("Net: HTTP redirect -> {} {}", next.method.as_str(), next.url).as_str());
+        Some(next)
+    }
+
+    /// Milliseconds to wait before attempt `attempt + 1`, or `None` when `response`
+    /// (`None` for a network failure) is final. Retryable statuses are only
+    /// retried for idempotent methods, failures only if the request may be
+    /// repeated (see `request_retry_allowed`).
+    pub fn retry_after(&self, attempt: usize, response: Option<&[u8]>, sent: bool) -> Option<u64> {
+        if attempt + 1 >= super::HTTP_RETRY_MAX_ATTEMPTS {
+            return None;
+        }
+        let backoff = super::http_retry_backoff_ms(attempt);
+        let reason = match response {
+            Some(bytes) => {
+                let status = parse_http_headers(bytes).status_code.unwrap_or(0);
+                if !self.method.is_idempotent() || !super::http_should_retry_status(status) {
+                    return None;
+                }
+                alloc::format!("status {}", status)
+            }
+            None if request_retry_allowed(self.method, sent) => String::from("network failure"),
+            None => return None,
+        };
+        println(
+            alloc::format!(
+                "Net: HTTP retry {}/{} after {} (backoff={} ms).",
+                attempt + 1,
+                super::HTTP_RETRY_MAX_ATTEMPTS - 1,
+                reason,
+                backoff
+            )
+            .as_str(),
+        );
+        Some(backoff)
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "net_request";
+
+    fn post_redirects_become_get_without_body() {
+        let request = HttpRequest::new(HttpMethod::Post, "http://example.com/form")
+            .header("Authorization", "Basic eA==")
+            .body("application/x-www-form-urlencoded", alloc::vec![b'a'; 4]);
+        let next = request.redirected(303, "/done").ok_or("303 sin redireccion")?;
+        crate::selftest::ensure_eq(next.method, HttpMethod::Get, "metodo")?;
+        crate::selftest::ensure_eq(next.url.as_str(), "http://example.com/done", "url")?;
+        crate::selftest::ensure(next.body.is_empty(), "cuerpo descartado")?;
+        crate::selftest::ensure_eq(next.headers.len(), 1, "solo queda Authorization")
+    }
+
+    fn redirects_keep_body_and_drop_credentials_across_hosts() {
+        let request = HttpRequest::new(HttpMethod::Put, "https://a.example/x")
+            .header("Authorization", "Bearer t")
+            .body("text/plain", alloc::vec![b'z'; 3]);
+        let next = request.redirected(307, "https://b.example/y").ok_or("307 sin redireccion")?;
+        crate::selftest::ensure_eq(next.method, HttpMethod::Put, "metodo")?;
+        crate::selftest::ensure_eq(next.body.len(), 3, "cuerpo")?;
+        crate::selftest::ensure(
+            !next.headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("authorization")),
+            "credenciales descartadas",
+        )?;
+        crate::selftest::ensure(request.redirected(200, "/").is_none(), "200 no redirige")
+    }
+
+    fn large_bodies_expect_continue() {
+        let small = HttpRequest::new(HttpMethod::Post, "http://h/").body("", alloc::vec![0; 16]);
+        let large = HttpRequest::new(HttpMethod::Post, "http://h/").body("", alloc::vec![0; EXPECT_CONTINUE_MIN_BYTES]);
+        crate::selftest::ensure(!small.expects_continue(), "cuerpo pequeno")?;
+        crate::selftest::ensure(large.expects_continue(), "cuerpo grande")
+    }
+
+    fn retries_respect_idempotency() {
+        let get = HttpRequest::get("http://h/");
+        let post = HttpRequest::new(HttpMethod::Post, "http://h/");
+        let busy = b"HTTP/1.1 503 Service Unavailable\r\n\r\n".as_slice();
+        crate::selftest::ensure(get.retry_after(0, Some(busy), true).is_some(), "GET tras 503")?;
+        crate::selftest::ensure(post.retry_after(0, Some(busy), true).is_none(), "POST tras 503")?;
+        crate::selftest::ensure(post.retry_after(0, None, false).is_some(), "POST sin enviar")?;
+        crate::selftest::ensure(post.retry_after(0, None, true).is_none(), "POST ya enviado")?;
+        crate::selftest::ensure(
+            get.retry_after(crate::net::HTTP_RETRY_MAX_ATTEMPTS - 1, None, true).is_none(),
+            "ultimo intento",
+        )
+    }
+}
@@ -0,0 +1,260 @@
+//! Traffic counters per interface and per process.
+//!
+//! Bytes and frames are counted where frames cross between smoltcp and a
+//! driver (`ReduxRxToken`/`ReduxTxToken`), so they cover everything on the
+//! wire including ARP, DNS and TLS overhead. Errors and drops come from the
+//! drivers: the Intel NIC's CRC/receive-error and missed-packet registers,
+//! and the VirtIO driver's incomplete frames, bad checksums and full TX ring.
+//!
+//! Per-process counts are charged by the userspace network API
+//! (`redux_http`) to the calling program, and show up in `net stats` and in
+//! the task manager's network column.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::perf::format_bytes;
+use crate::spinlock::SpinLock;
+
+/// Programs tracked at once; the one with the least traffic makes room.
+const PROCESS_TRAFFIC_MAX: usize = 32;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Iface {
+    Virtio = 0,
+    Intel = 1,
+}
+
+struct IfaceCounters {
+    rx_bytes: AtomicU64,
+    rx_packets: AtomicU64,
+    tx_bytes: AtomicU64,
+    tx_packets: AtomicU64,
+}
+
+impl IfaceCounters {
+    const fn new() -> Self {
+        Self {
+            rx_bytes: AtomicU64::new(0),
+            rx_packets: AtomicU64::new(0),
+            tx_bytes: AtomicU64::new(0),
+            tx_packets: AtomicU64::new(0),
+        }
+    }
+}
+
+static IFACES: [IfaceCounters; 2] = [IfaceCounters::new(), IfaceCounters::new()];
+
+pub(super) fn count_rx(iface: Iface, len: usize) {
+    let counters = &IFACES[iface as usize];
+    counters.rx_bytes.fetch_add(len as u64, Ordering::Relaxed);
+    counters.rx_packets.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(super) fn count_tx(iface: Iface, len: usize) {
+    let counters = &IFACES[iface as usize];
+    counters.tx_bytes.fetch_add(len as u64, Ordering::Relaxed);
+    counters.tx_packets.fetch_add(1, Ordering::Relaxed);
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IfaceStats {
+    pub name: &'static str,
+    pub mac: Option<[u8; 6]>,
+    pub link_up: bool,
+    pub rx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_bytes: u64,
+    pub tx_packets: u64,
+    pub rx_errors: u64,
+    pub rx_drops: u64,
+    pub tx_drops: u64,
+}
+
+fn iface_stats(iface: Iface, name: &'static str, mac: Option<[u8; 6]>, link_up: bool) -> IfaceStats {
+    let counters = &IFACES[iface as usize];
+    IfaceStats {
+        name,
+        mac,
+        link_up,
+        rx_bytes: counters.rx_bytes.load(Ordering::Relaxed),
+        rx_packets: counters.rx_packets.load(Ordering::Relaxed),
+        tx_bytes: counters.tx_bytes.load(Ordering::Relaxed),
+        tx_packets: counters.tx_packets.load(Ordering::Relaxed),
+        ..IfaceStats::default()
+    }
+}
+
+/// Counters of every interface present, since boot.
+pub fn interfaces() -> Vec<IfaceStats> {
+    let mut out = Vec::new();
+    if let Some(model) = crate::intel_net::get_model_name() {
+        let mut stats = iface_stats(
+            Iface::Intel,
+            model,
+            crate::intel_net::get_mac_address(),
+            crate::intel_net::is_link_up(),
+        );
+        let (rx_errors, rx_missed) = crate::intel_net::error_counters();
+        stats.rx_errors = rx_errors;
+        stats.rx_drops = rx_missed;
+        out.push(stats);
+    }
+    let virtio = crate::virtio::net::GLOBAL_NET
+        .lock()
+        .as_ref()
+        .map(|drv| (drv.mac_address(), drv.is_link_up(), drv.stats()));
+    if let Some((mac, link_up, driver)) = virtio {
+        let mut stats = iface_stats(Iface::Virtio, "VirtIO Net", Some(mac), link_up);
+        stats.rx_errors = driver.rx_incomplete;
+        stats.rx_drops = driver.rx_csum_dropped;
+        stats.tx_drops = driver.tx_ring_full;
+        out.push(stats);
+    }
+    out
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProcessTraffic {
+    pub owner: String,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub requests: u64,
+}
+
+static PROCESSES: SpinLock<Vec<ProcessTraffic>> = SpinLock::new(Vec::new());
+
+fn charge_into(table: &mut Vec<ProcessTraffic>, owner: &str, rx: u64, tx: u64) {
+    if let Some(entry) = table.iter_mut().find(|entry| entry.owner == owner) {
+        entry.rx_bytes = entry.rx_bytes.saturating_add(rx);
+        entry.tx_bytes = entry.tx_bytes.saturating_add(tx);
+        entry.requests = entry.requests.saturating_add(1);
+        return;
+    }
+    if table.len() >= PROCESS_TRAFFIC_MAX {
+        if let Some(idx) = table
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, entry)| entry.rx_bytes.saturating_add(entry.tx_bytes))
+            .map(|(idx, _)| idx)
+        {
+            table.swap_remove(idx);
+        }
+    }
+    table.push(ProcessTraffic {
+        owner: String::from(owner),
+        rx_bytes: rx,
+        tx_bytes: tx,
+        requests: 1,
+    });
+}
+
+/// Charge one request's traffic to `owner` (a thread or program name).
+pub fn charge_process(owner: &str, rx: u64, tx: u64) {
+    charge_into(&mut PROCESSES.lock(), owner, rx, tx);
+}
+
+/// `(rx, tx)` bytes charged to `owner`.
+pub fn process_bytes(owner: &str) -> (u64, u64) {
+    PROCESSES
+        .lock()
+        .iter()
+        .find(|entry| entry.owner == owner)
+        .map(|entry| (entry.rx_bytes, entry.tx_bytes))
+        .unwrap_or((0, 0))
+}
+
+/// Every tracked program, busiest first.
+pub fn processes() -> Vec<ProcessTraffic> {
+    let mut rows = PROCESSES.lock().clone();
+    rows.sort_by(|a, b| {
+        b.rx_bytes
+            .saturating_add(b.tx_bytes)
+            .cmp(&a.rx_bytes.saturating_add(a.tx_bytes))
+            .then(a.owner.cmp(&b.owner))
+    });
+    rows
+}
+
+pub(crate) fn mac_text(mac: &[u8; 6]) -> String {
+    alloc::format!(
+        "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+        mac[0],
+        mac[1],
+        mac[2],
+        mac[3],
+        mac[4],
+        mac[5]
+    )
+}
+
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    if !args.trim().is_empty() {
+        out.push(String::from("Uso: net stats"));
+        return out;
+    }
+    let interfaces = interfaces();
+    if interfaces.is_empty() {
+        out.push(String::from("net stats: sin interfaces de red"));
+    }
+    for iface in interfaces.iter() {
+        out.push(alloc::format!(
+            "{} ({}) enlace {}",
+            iface.name,
+            iface
+                .mac
+                .as_ref()
+                .map(mac_text)
+                .unwrap_or_else(|| String::from("sin MAC")),
+            if iface.link_up { "up" } else { "down" }
+        ));
+        out.push(alloc::format!(
+            "  RX {} en {} tramas, {} errores, {} descartadas",
+            format_bytes(iface.rx_bytes),
+            iface.rx_packets,
+            iface.rx_errors,
+            iface.rx_drops
+        ));
+        out.push(alloc::format!(
+            "  TX {} en {} tramas, {} descartadas",
+            format_bytes(iface.tx_bytes),
+            iface.tx_packets,
+            iface.tx_drops
+        ));
+    }
+    let processes = processes();
+    if processes.is_empty() {
+        out.push(String::from("net stats: ningun programa ha usado la red"));
+        return out;
+    }
+    out.push(String::from("Programa              Recibido     Enviado  Peticiones"));
+    for row in processes.iter() {
+        out.push(alloc::format!(
+            "{:<20} {:>10} {:>10} {:>10}",
+            row.owner,
+            format_bytes(row.rx_bytes),
+            format_bytes(row.tx_bytes),
+            row.requests
+        ));
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "net_stats";
+
+    fn process_table_accumulates_and_evicts() {
+        let mut table = Vec::new();
+        charge_into(&mut table, "browser", 1_000, 100);
+        charge_into(&mut table, "browser", 500, 50);
+        crate::selftest::ensure_eq(table.len(), 1, "una fila por programa")?;
+        crate::selftest::ensure_eq((table[0].rx_bytes, table[0].tx_bytes, table[0].requests), (1_500, 150, 2), "suma")?;
+        for i in 0..PROCESS_TRAFFIC_MAX {
+            charge_into(&mut table, alloc::format!("app{}", i).as_str(), 10_000 + i as u64, 0);
+        }
+        crate::selftest::ensure_eq(table.len(), PROCESS_TRAFFIC_MAX, "tabla acotada")?;
+        crate::selftest::ensure(table.iter().all(|entry| entry.owner != "browser"), "sale el de menos trafico")
+    }
+}
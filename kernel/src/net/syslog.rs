@@ -0,0 +1,329 @@
+//! RFC 5424 syslog forwarding of the kernel log ring.
+//!
+//! Configured with `log remote <ip>[:port] [udp|tcp]`. Records are drained
+//! from `crate::klog` from inside `net::poll`, so forwarding never blocks the
+//! caller that produced the log line. TCP uses RFC 6587 octet-counting framing.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use smoltcp::iface::{Interface, SocketHandle, SocketSet};
+use smoltcp::socket::{tcp, udp};
+use smoltcp::wire::{IpAddress, IpEndpoint};
+
+use crate::klog::{KlogRecord, Level};
+use crate::spinlock::SpinLock;
+
+const SYSLOG_DEFAULT_PORT: u16 = 514;
+const SYSLOG_LOCAL_UDP_PORT: u16 = 40514;
+const SYSLOG_MAX_PER_POLL: usize = 16;
+const SYSLOG_TCP_RETRY_TICKS: u64 = 3_000;
+const SYSLOG_FACILITY_KERN: u8 = 0;
+const SYSLOG_HOSTNAME: &str = "zenox";
+const SYSLOG_APP_NAME: &str = "kernel";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SyslogTransport {
+    Udp,
+    Tcp,
+}
+
+impl SyslogTransport {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            SyslogTransport::Udp => "udp",
+            SyslogTransport::Tcp => "tcp",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct SyslogConfig {
+    addr: [u8; 4],
+    port: u16,
+    transport: SyslogTransport,
+}
+
+struct SyslogState {
+    config: Option<SyslogConfig>,
+    handle: Option<SocketHandle>,
+    handle_transport: SyslogTransport,
+    next_seq: u64,
+    sent: u64,
+    failed: u64,
+    last_connect_tick: u64,
+    min_level: Level,
+}
+
+static SYSLOG: SpinLock<SyslogState> = SpinLock::new(SyslogState {
+    config: None,
+    handle: None,
+    handle_transport: SyslogTransport::Udp,
+    next_seq: 0,
+    sent: 0,
+    failed: 0,
+    last_connect_tick: 0,
+    min_level: Level::Debug,
+});
+
+fn civil_from_days(days: i64) -> (i32, u8, u8) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = mp + if mp < 10 { 3 } else { -9 };
+    let year = y + if month <= 2 { 1 } else { 0 };
+    (year as i32, month as u8, day as u8)
+}
+
+/// RFC 3339 UTC timestamp for the wall-clock time at which `record` was logged.
+fn record_timestamp(record: &KlogRecord) -> String {
+    let now_ms = crate::timer::wall_clock_unix_millis();
+    let age_ms = crate::timer::snapshot().uptime_ms.saturating_sub(record.uptime_ms) as i64;
+    let unix_ms = now_ms.saturating_sub(age_ms);
+    let secs = unix_ms.div_euclid(1000);
+    let millis = unix_ms.rem_euclid(1000);
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let sod = secs.rem_euclid(86_400);
+    alloc::format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        sod / 3600,
+        (sod / 60) % 60,
+        sod % 60,
+        millis
+    )
+}
+
+fn sanitize_message(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+/// Build one RFC 5424 message: `<PRI>1 TIMESTAMP HOST APP PROCID MSGID SD MSG`.
+pub fn format_rfc5424(record: &KlogRecord) -> String {
+    let pri = (SYSLOG_FACILITY_KERN as u16) * 8 + record.level as u16;
+    alloc::format!(
+        "<{}>1 {} {} {} - seq{} - {}",
+        pri,
+        record_timestamp(record),
+        SYSLOG_HOSTNAME,
+        SYSLOG_APP_NAME,
+        record.seq,
+        sanitize_message(record.text.as_str())
+    )
+}
+
+fn release_socket(sockets: &mut SocketSet<'_>, state: &mut SyslogState) {
+    if let Some(handle) = state.handle.take() {
+        if state.handle_transport == SyslogTransport::Tcp {
+            sockets.get_mut::<tcp::Socket>(handle).abort();
+        }
+        sockets.remove(handle);
+    }
+}
+
+fn open_socket(
+    iface: &mut Interface,
+    sockets: &mut SocketSet<'_>,
+    state: &mut SyslogState,
+    config: SyslogConfig,
+    now_ticks: u64,
+) -> Option<SocketHandle> {
+    match config.transport {
+        SyslogTransport::Udp => {
+            let rx = udp::PacketBuffer::new(alloc::vec![udp::PacketMetadata::EMPTY; 1], alloc::vec![0u8; 64]);
+            let tx = udp::PacketBuffer::new(
+                alloc::vec![udp::PacketMetadata::EMPTY; SYSLOG_MAX_PER_POLL],
+                alloc::vec![0u8; 16 * 1024],
+            );
+            let mut socket = udp::Socket::new(rx, tx);
+            if socket.bind(SYSLOG_LOCAL_UDP_PORT).is_err() {
+                return None;
+            }
+            let handle = sockets.add(socket);
+            state.handle = Some(handle);
+            state.handle_transport = SyslogTransport::Udp;
+            Some(handle)
+        }
+        SyslogTransport::Tcp => {
+            if now_ticks.saturating_sub(state.last_connect_tick) < SYSLOG_TCP_RETRY_TICKS
+                && state.last_connect_tick != 0
+            {
+                return None;
+            }
+            state.last_connect_tick = now_ticks;
+            let mut socket = tcp::Socket::new(
+                tcp::SocketBuffer::new(alloc::vec![0u8; 512]),
+                tcp::SocketBuffer::new(alloc::vec![0u8; 16 * 1024]),
+            );
+            let remote = IpAddress::Ipv4(super::ipv4_from_octets(config.addr));
+            let local_port = 50000 + (now_ticks % 10_000) as u16;
+            if socket.connect(iface.context(), (remote, config.port), local_port).is_err() {
+                state.failed = state.failed.saturating_add(1);
+                return None;
+            }
+            let handle = sockets.add(socket);
+            state.handle = Some(handle);
+            state.handle_transport = SyslogTransport::Tcp;
+            Some(handle)
+        }
+    }
+}
+
+/// Forward pending log records. Called from `net::poll` after the interface poll.
+pub fn pump(iface: &mut Interface, sockets: &mut SocketSet<'_>, now_ticks: u64) {
+    let mut guard = SYSLOG.lock();
+    let state = &mut *guard;
+    let Some(config) = state.config else {
+        release_socket(sockets, state);
+        return;
+    };
+    let has_ipv4 = iface
+        .ip_addrs()
+        .iter()
+        .any(|cidr| matches!(cidr.address(), IpAddress::Ipv4(ip) if !ip.is_unspecified()));
+    if !has_ipv4 {
+        return;
+    }
+
+    if state.handle.is_some() && state.handle_transport != config.transport {
+        release_socket(sockets, state);
+    }
+    let handle = match state.handle {
+        Some(handle) => handle,
+        None => match open_socket(iface, sockets, state, config, now_ticks) {
+            Some(handle) => handle,
+            None => return,
+        },
+    };
+
+    let records = crate::klog::records_since(state.next_seq, SYSLOG_MAX_PER_POLL);
+    if records.is_empty() {
+        return;
+    }
+
+    let remote = IpEndpoint::new(IpAddress::Ipv4(super::ipv4_from_octets(config.addr)), config.port);
+    for record in records.iter() {
+        if record.level > state.min_level {
+            state.next_seq = record.seq.saturating_add(1);
+            continue;
+        }
+        let message = format_rfc5424(record);
+        let sent = match config.transport {
+            SyslogTransport::Udp => {
+                let socket = sockets.get_mut::<udp::Socket>(handle);
+                socket.can_send() && socket.send_slice(message.as_bytes(), remote).is_ok()
+            }
+            SyslogTransport::Tcp => {
+                let socket = sockets.get_mut::<tcp::Socket>(handle);
+                if !socket.is_active() {
+                    release_socket(sockets, state);
+                    return;
+                }
+                if !socket.may_send() {
+                    return;
+                }
+                let framed = alloc::format!("{} {}", message.len(), message);
+                if socket.send_capacity() - socket.send_queue() < framed.len() {
+                    return;
+                }
+                socket.send_slice(framed.as_bytes()).is_ok()
+            }
+        };
+        if !sent {
+            state.failed = state.failed.saturating_add(1);
+            return;
+        }
+        state.sent = state.sent.saturating_add(1);
+        state.next_seq = record.seq.saturating_add(1);
+    }
+}
+
+fn parse_target(text: &str) -> Option<([u8; 4], u16)> {
+    let (host, port) = match text.split_once(':') {
+        Some((host, port)) => (host, port.parse::<u16>().ok().filter(|p| *p != 0)?),
+        None => (text, SYSLOG_DEFAULT_PORT),
+    };
+    Some((super::parse_ipv4_octets(host)?, port))
+}
+
+pub fn set_remote(addr: [u8; 4], port: u16, transport: SyslogTransport) {
+    let mut state = SYSLOG.lock();
+    state.config = Some(SyslogConfig { addr, port, transport });
+    // Forward whatever is still in the ring so boot diagnostics reach the collector.
+    state.next_seq = crate::klog::oldest_seq();
+    state.last_connect_tick = 0;
+}
+
+pub fn disable_remote() {
+    SYSLOG.lock().config = None;
+}
+
+pub fn set_min_level(level: Level) {
+    SYSLOG.lock().min_level = level;
+}
+
+pub fn status_line() -> String {
+    let state = SYSLOG.lock();
+    match state.config {
+        Some(config) => alloc::format!(
+            "Syslog: {}.{}.{}.{}:{} via {} nivel<={} enviados={} fallos={} pendientes={}",
+            config.addr[0],
+            config.addr[1],
+            config.addr[2],
+            config.addr[3],
+            config.port,
+            config.transport.as_str(),
+            state.min_level.as_str(),
+            state.sent,
+            state.failed,
+            crate::klog::next_seq().saturating_sub(state.next_seq.max(crate::klog::oldest_seq()))
+        ),
+        None => String::from("Syslog: reenvio remoto desactivado."),
+    }
+}
+
+/// Handles the arguments after `log remote`.
+pub fn command_lines(args: &[&str]) -> Vec<String> {
+    let mut out = Vec::new();
+    let first = args.first().copied().unwrap_or("status");
+
+    if first.eq_ignore_ascii_case("status") {
+        out.push(status_line());
+    } else if first.eq_ignore_ascii_case("off") {
+        disable_remote();
+        out.push(String::from("Syslog: reenvio remoto desactivado."));
+    } else if first.eq_ignore_ascii_case("level") {
+        match args.get(1).and_then(|l| Level::parse(l)) {
+            Some(level) => {
+                set_min_level(level);
+                out.push(alloc::format!("Syslog: nivel minimo -> {}", level.as_str()));
+            }
+            None => out.push(String::from("Uso: log remote level <emerg|alert|crit|err|warn|notice|info|debug>")),
+        }
+    } else {
+        let transport = match args.get(1).copied() {
+            None => Some(SyslogTransport::Udp),
+            Some(t) if t.eq_ignore_ascii_case("udp") => Some(SyslogTransport::Udp),
+            Some(t) if t.eq_ignore_ascii_case("tcp") => Some(SyslogTransport::Tcp),
+            Some(_) => None,
+        };
+        match (parse_target(first), transport) {
+            (Some((addr, port)), Some(transport)) => {
+                set_remote(addr, port, transport);
+                out.push(status_line());
+            }
+            _ => out.push(String::from(
+                "Uso: log remote <ip[:puerto]> [udp|tcp] | log remote level <nivel> | log remote off | log remote status",
+            )),
+        }
+    }
+    out
+}
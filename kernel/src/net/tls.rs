@@ -6,6 +6,7 @@ use rustls::pki_types::{ServerName, UnixTime};
 
 use crate::println;
 
+use rustls::client::danger::ServerCertVerifier;
 use rustls::client::UnbufferedClientConnection;
 use rustls::time_provider::TimeProvider;
 use rustls::unbuffered::ConnectionState;
@@ -66,6 +67,27 @@ impl TimeProvider for KernelTimeProvider {
     }
 }
 
+/// Client config that verifies against the webpki roots, without ALPN.
+pub fn webpki_client_config() -> ClientConfig {
+    let mut root_store = RootCertStore::empty();
+    root_store.extend(
+        webpki_roots::TLS_SERVER_ROOTS
+            .iter()
+            .cloned()
+    );
+
+    let provider = crate::crypto::provider::tls_provider();
+
+    ClientConfig::builder_with_details(
+        Arc::new(provider),
+        Arc::new(KernelTimeProvider)
+    )
+    .with_safe_default_protocol_versions()
+    .unwrap()
+    .with_root_certificates(root_store)
+    .with_no_client_auth()
+}
+
 pub enum HandshakeStatus {
     Done,
     InProgress,
@@ -80,29 +102,51 @@ pub struct TlsConnection {
 
 impl TlsConnection {
     pub fn new(hostname: &str) -> Option<Self> {
-        let mut root_store = RootCertStore::empty();
-        root_store.extend(
-            webpki_roots::TLS_SERVER_ROOTS
-                .iter()
-                .cloned()
-        );
-
-        let provider = rustls_rustcrypto::provider();
-        
-        let mut config = ClientConfig::builder_with_details(
+        let mut config = webpki_client_config();
+        // Offer HTTP/2 first, then HTTP/1.1 fallback via ALPN.
+        config.alpn_protocols = alloc::vec![
+            TLS_ALPN_H2.to_vec(),
+            TLS_ALPN_HTTP11.to_vec(),
+        ];
+
+        Self::with_config(Arc::new(config), hostname)
+    }
+
+    /// Like `new` but offering only HTTP/1.1, for requests the HTTP/2 path
+    /// cannot send (anything but GET).
+    pub fn new_http11(hostname: &str) -> Option<Self> {
+        let mut config = webpki_client_config();
+        config.alpn_protocols = alloc::vec![TLS_ALPN_HTTP11.to_vec()];
+
+        Self::with_config(Arc::new(config), hostname)
+    }
+
+    /// Connection built from a config shared by several connections to the
+    /// same server. rustls keeps its session cache in the config, so later
+    /// connections resume the first one's session (FTPS data channels).
+    pub fn new_shared(hostname: &str, config: &Arc<ClientConfig>) -> Option<Self> {
+        Self::with_config(config.clone(), hostname)
+    }
+
+    /// Connection whose certificate check is left to `verifier` instead of
+    /// the webpki roots (Gemini pins self-signed certificates on first use).
+    /// No ALPN is offered.
+    pub fn new_with_verifier(hostname: &str, verifier: Arc<dyn ServerCertVerifier>) -> Option<Self> {
+        let provider = crate::crypto::provider::tls_provider();
+        let config = ClientConfig::builder_with_details(
             Arc::new(provider),
             Arc::new(KernelTimeProvider)
         )
         .with_safe_default_protocol_versions()
         .unwrap()
-        .with_root_certificates(root_store)
+        .dangerous()
+        .with_custom_certificate_verifier(verifier)
         .with_no_client_auth();
-        // Offer HTTP/2 first, then HTTP/1.1 fallback via ALPN.
-        config.alpn_protocols = alloc::vec![
-            TLS_ALPN_H2.to_vec(),
-            TLS_ALPN_HTTP11.to_vec(),
-        ];
-            
+
+        Self::with_config(Arc::new(config), hostname)
+    }
+
+    fn with_config(config: Arc<ClientConfig>, hostname: &str) -> Option<Self> {
         let server_name = match ServerName::try_from(hostname) {
             Ok(n) => n.to_owned(),
             Err(_) => {
@@ -111,7 +155,7 @@ impl TlsConnection {
             }
         };
 
-        match UnbufferedClientConnection::new(Arc::new(config), server_name) {
+        match UnbufferedClientConnection::new(config, server_name) {
             Ok(conn) => Some(TlsConnection {
                 conn,
                 incoming_tls: [0u8; 8192],
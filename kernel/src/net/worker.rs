@@ -0,0 +1,948 @@
+//! Background HTTP requests, advanced from `net::poll`.
+//!
+//! `HttpRequest::send` blocks its caller until the response is in and keeps
+//! the desktop alive through a `pump_ui` callback. `submit` instead queues
+//! the request and returns at once; `pump`, called from `net::poll` like the
+//! syslog forwarder, moves every request one non-blocking step at a time
+//! through resolve, connect, TLS handshake, send and receive. Callers either
+//! poll the request id (`poll`) or hand over a completion callback
+//! (`submit_with`), so the compositor and the shell never wait on the
+//! network inside a frame.
+//!
+//! Background requests speak HTTP/1.1: plain, through the HTTPS proxy, or
+//! TLS with ALPN `http/1.1`. They share the keep-alive pool, the cache and
+//! the cookie jar with the blocking client and follow the same retry and
+//! redirect rules (`HttpRequest::retry_after`, `HttpRequest::next_hop`).
+//!
+//! Executor tasks use `fetch`, a future over `submit` and `poll` that `pump`
+//! wakes when a request finishes.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use smoltcp::iface::{Interface, SocketHandle, SocketSet};
+use smoltcp::socket::{dns, tcp};
+use smoltcp::wire::{IpAddress, Ipv4Address};
+
+use super::request::{HttpMethod, HttpRequest, EXPECT_CONTINUE_WAIT_MS};
+use super::{pool, tls, Http1Reader, HttpTarget};
+use crate::println;
+use crate::spinlock::SpinLock;
+use crate::sync::KernelCell;
+use redux_netparse::http::{find_http_header_end, interim_responses_len};
+
+pub type RequestId = u64;
+
+/// Requests queued, in flight or waiting to be collected.
+const WORKER_MAX_JOBS: usize = 32;
+/// Requests holding a socket at once. The socket set has a fixed number of
+/// slots shared with DHCP, DNS, syslog, the pool and the blocking client.
+const WORKER_MAX_ACTIVE: usize = 3;
+/// State transitions per request and `pump`, so a fast response completes
+/// within one frame without one request monopolising it.
+const WORKER_STEPS_PER_PUMP: usize = 4;
+/// Finished requests nobody collected are dropped after this long.
+const WORKER_UNCLAIMED_TICKS: u64 = 6_000;
+
+pub enum Poll {
+    Pending,
+    /// The response (head and decoded body), or `None` when the request
+    /// failed, was cancelled or the id is unknown.
+    Ready(Option<Vec<u8>>),
+}
+
+pub type Callback = Box<dyn FnOnce(RequestId, Option<Vec<u8>>)>;
+
+struct Conn {
+    handle: SocketHandle,
+    tls: Option<Box<tls::TlsConnection>>,
+    /// Requests already served on this socket, for the pool.
+    served: u32,
+    /// Response bytes that arrived before the body went out (`100 Continue`
+    /// or an early final response).
+    early: Vec<u8>,
+}
+
+enum Outgoing {
+    Head(Vec<u8>),
+    Body,
+}
+
+enum Stage {
+    /// Waiting for a free socket, or for the retry backoff to end.
+    Waiting {
+        until: u64,
+    },
+    Resolve {
+        query: Option<dns::QueryHandle>,
+        since: u64,
+    },
+    Connect {
+        handle: SocketHandle,
+        since: u64,
+    },
+    Handshake {
+        handle: SocketHandle,
+        tls: Box<tls::TlsConnection>,
+        since: u64,
+    },
+    Send {
+        conn: Conn,
+        out: Outgoing,
+        offset: usize,
+        since: u64,
+    },
+    AwaitContinue {
+        conn: Conn,
+        since: u64,
+    },
+    Receive {
+        conn: Conn,
+        reader: Http1Reader,
+        body_sent: bool,
+        last_data: u64,
+    },
+    Done {
+        response: Option<Vec<u8>>,
+        at: u64,
+    },
+}
+
+impl Stage {
+    /// The stage owns (or is about to own) a TCP socket.
+    fn is_active(&self) -> bool {
+        !matches!(self, Stage::Waiting { .. } | Stage::Done { .. })
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Stage::Waiting { .. } => "en cola",
+            Stage::Resolve { .. } => "dns",
+            Stage::Connect { .. } => "conectando",
+            Stage::Handshake { .. } => "tls",
+            Stage::Send { .. } | Stage::AwaitContinue { .. } => "enviando",
+            Stage::Receive { .. } => "recibiendo",
+            Stage::Done { .. } => "terminada",
+        }
+    }
+
+    /// Release whatever network resource the stage holds.
+    fn release(self, sockets: &mut SocketSet<'_>) {
+        match self {
+            Stage::Resolve { query: Some(query), .. } => {
+                if let Some(dns_handle) = unsafe { *super::DNS_HANDLE.get() } {
+                    sockets.get_mut::<dns::Socket>(dns_handle).cancel_query(query);
+                }
+            }
+            Stage::Connect { handle, .. } | Stage::Handshake { handle, .. } => {
+                sockets.remove(handle);
+            }
+            Stage::Send { conn, .. } | Stage::AwaitContinue { conn, .. } | Stage::Receive { conn, .. } => {
+                sockets.remove(conn.handle);
+            }
+            _ => {}
+        }
+    }
+}
+
+struct Job {
+    id: RequestId,
+    request: HttpRequest,
+    target: Option<HttpTarget>,
+    attempt: usize,
+    hops: usize,
+    /// The request head reached the socket in the current attempt.
+    sent: bool,
+    stage: Stage,
+    callback: Option<Callback>,
+    queued_at: u64,
+    /// `HttpRequest::replay_key` of the request as submitted; `request`
+    /// changes on redirects.
+    replay_key: String,
+}
+
+#[derive(Clone, Copy, Default)]
+struct WorkerStats {
+    submitted: u64,
+    completed: u64,
+    failed: u64,
+    cancelled: u64,
+}
+
+// Jobs own their callbacks and sockets of `super::SOCKETS`, so they follow
+// the socket set's kernel-thread-only rule.
+static JOBS: KernelCell<Vec<Job>> = KernelCell::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static WORKER_STATS: SpinLock<WorkerStats> = SpinLock::new(WorkerStats {
+    submitted: 0,
+    completed: 0,
+    failed: 0,
+    cancelled: 0,
+});
+/// Wakes `Fetch` futures whenever a request finishes.
+static FINISHED: crate::executor::WaitQueue = crate::executor::WaitQueue::new();
+/// Plaintext buffer for TLS reads, shared by every job.
+static TLS_READ_BUF: KernelCell<[u8; super::TLS_MAX_RECORD_PLAINTEXT]> =
+    KernelCell::new([0; super::TLS_MAX_RECORD_PLAINTEXT]);
+
+fn enqueue(request: HttpRequest, callback: Option<Callback>) -> Option<RequestId> {
+    // SAFETY: kernel thread; only checks that the stack is up.
+    if unsafe { super::IFACE.get().is_none() || super::SOCKETS.get().is_none() } {
+        println("Net: Stack not initialized.");
+        return None;
+    }
+    // SAFETY: kernel thread, and no other job borrow is alive here.
+    let jobs = unsafe { JOBS.get_mut() };
+    if jobs.len() >= WORKER_MAX_JOBS {
+        println("Net: background request queue full.");
+        return None;
+    }
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let replay_key = request.replay_key();
+    // A replay answers at the next pump, without a socket.
+    let stage = match crate::replay::network_reply(replay_key.as_str()) {
+        Some(response) => Stage::Done {
+            response,
+            at: crate::timer::ticks(),
+        },
+        None => Stage::Waiting { until: 0 },
+    };
+    jobs.push(Job {
+        id,
+        request,
+        target: None,
+        attempt: 0,
+        hops: 0,
+        sent: false,
+        stage,
+        callback,
+        queued_at: crate::timer::ticks(),
+        replay_key,
+    });
+    WORKER_STATS.lock().submitted += 1;
+    Some(id)
+}
+
+/// Queue `request`; collect the result with `poll`. `None` when the network
+/// stack is down or the queue is full.
+pub fn submit(request: HttpRequest) -> Option<RequestId> {
+    enqueue(request, None)
+}
+
+/// Queue `request` and call `callback` from `net::poll` when it finishes.
+pub fn submit_with(
+    request: HttpRequest,
+    callback: impl FnOnce(RequestId, Option<Vec<u8>>) + 'static,
+) -> Option<RequestId> {
+    enqueue(request, Some(Box::new(callback)))
+}
+
+/// State of request `id`. `Ready` hands the result over and forgets the id.
+pub fn poll(id: RequestId) -> Poll {
+    // SAFETY: kernel thread, and no other job borrow is alive here.
+    let jobs = unsafe { JOBS.get_mut() };
+    let Some(idx) = jobs.iter().position(|job| job.id == id) else {
+        return Poll::Ready(None);
+    };
+    if !matches!(jobs[idx].stage, Stage::Done { .. }) {
+        return Poll::Pending;
+    }
+    match jobs.remove(idx).stage {
+        Stage::Done { response, .. } => Poll::Ready(response),
+        _ => Poll::Ready(None),
+    }
+}
+
+/// Future for a background request, resolving to what `poll` would return.
+/// Dropping it cancels the request.
+pub struct Fetch {
+    id: Option<RequestId>,
+}
+
+/// Queue `request` for an executor task. `None` as for `submit`.
+pub fn fetch(request: HttpRequest) -> Option<Fetch> {
+    submit(request).map(|id| Fetch { id: Some(id) })
+}
+
+impl Fetch {
+    pub fn id(&self) -> Option<RequestId> {
+        self.id
+    }
+}
+
+impl core::future::Future for Fetch {
+    type Output = Option<Vec<u8>>;
+
+    fn poll(mut self: core::pin::Pin<&mut Self>, cx: &mut core::task::Context<'_>) -> core::task::Poll<Self::Output> {
+        let Some(id) = self.id else {
+            return core::task::Poll::Ready(None);
+        };
+        match poll(id) {
+            Poll::Pending => {
+                FINISHED.register(cx.waker());
+                core::task::Poll::Pending
+            }
+            Poll::Ready(response) => {
+                self.id = None;
+                core::task::Poll::Ready(response)
+            }
+        }
+    }
+}
+
+impl Drop for Fetch {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            cancel(id);
+        }
+    }
+}
+
+/// Abort request `id`, closing its socket; its callback is not called.
+pub fn cancel(id: RequestId) -> bool {
+    // SAFETY: kernel thread, and no other job borrow is alive here.
+    let jobs = unsafe { JOBS.get_mut() };
+    let Some(idx) = jobs.iter().position(|job| job.id == id) else {
+        return false;
+    };
+    let job = jobs.remove(idx);
+    // A queued job holds no socket, so cancelling it is safe inside
+    // `net::poll` (a `Fetch` dropped there).
+    if job.stage.is_active() {
+        if let Some(sockets) = unsafe { super::SOCKETS.get_mut().as_mut() } {
+            job.stage.release(sockets);
+        }
+    }
+    WORKER_STATS.lock().cancelled += 1;
+    true
+}
+
+/// Advance every request; called from `net::poll` after `iface.poll`.
+pub fn pump(iface: &mut Interface, sockets: &mut SocketSet<'static>, now_ticks: u64) {
+    let mut finished: Vec<(RequestId, Callback, Option<Vec<u8>>)> = Vec::new();
+    let mut any_done = false;
+    // SAFETY: kernel thread; callbacks, which may submit, run after this
+    // borrow ends.
+    let jobs = unsafe { JOBS.get_mut() };
+    if jobs.is_empty() {
+        return;
+    }
+    let mut active = jobs.iter().filter(|job| job.stage.is_active()).count();
+    for job in jobs.iter_mut() {
+        for _ in 0..WORKER_STEPS_PER_PUMP {
+            let was_active = job.stage.is_active();
+            let was_done = matches!(job.stage, Stage::Done { .. });
+            let stage = core::mem::replace(&mut job.stage, Stage::Waiting { until: u64::MAX });
+            let (next, progressed) = step(job, stage, iface, sockets, now_ticks, active < WORKER_MAX_ACTIVE);
+            job.stage = next;
+            any_done |= !was_done && matches!(job.stage, Stage::Done { .. });
+            match (was_active, job.stage.is_active()) {
+                (false, true) => active += 1,
+                (true, false) => active -= 1,
+                _ => {}
+            }
+            if !progressed {
+                break;
+            }
+        }
+    }
+
+    let mut i = 0usize;
+    while i < jobs.len() {
+        let Stage::Done { at, .. } = jobs[i].stage else {
+            i += 1;
+            continue;
+        };
+        if jobs[i].callback.is_some() {
+            let job = jobs.remove(i);
+            if let (Some(callback), Stage::Done { response, .. }) = (job.callback, job.stage) {
+                finished.push((job.id, callback, response));
+            }
+        } else if now_ticks.saturating_sub(at) > WORKER_UNCLAIMED_TICKS {
+            jobs.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+    if any_done {
+        FINISHED.wake_all();
+    }
+    // Outside the job list: a callback may submit the next request.
+    for (id, callback, response) in finished {
+        callback(id, response);
+    }
+}
+
+/// One transition of `job` from `stage`: the next stage and whether to try
+/// another step in this pump. `may_start` is false while the active limit is
+/// reached.
+fn step(
+    job: &mut Job,
+    stage: Stage,
+    iface: &mut Interface,
+    sockets: &mut SocketSet<'static>,
+    now: u64,
+    may_start: bool,
+) -> (Stage, bool) {
+    let timeout = super::ms_to_ticks(job.request.timeout_ms);
+    match stage {
+        Stage::Waiting { until } => {
+            if now < until || !may_start {
+                return (Stage::Waiting { until }, false);
+            }
+            (begin_attempt(job, sockets, now), true)
+        }
+
+        Stage::Resolve { query, since } => {
+            let Some(host) = job.target.as_ref().map(|t| t.host.clone()) else {
+                return (finish_attempt(job, None, now), true);
+            };
+            if let Ok(ip) = host.parse::<Ipv4Address>() {
+                return (open_socket(job, iface, sockets, ip, now), true);
+            }
+            let Some(dns_handle) = (unsafe { *super::DNS_HANDLE.get() }) else {
+                return (finish_attempt(job, None, now), true);
+            };
+            let dns_socket = sockets.get_mut::<dns::Socket>(dns_handle);
+            let Some(query) = query else {
+                let name = super::dhcp::qualify_host(host.as_str());
+                // Every query slot may be busy; try again next pump.
+                return match dns_socket.start_query(iface.context(), name.as_str(), smoltcp::wire::DnsQueryType::A) {
+                    Ok(query) => {
+                        println(alloc::format!("Net: Resolving {}...", name).as_str());
+                        (
+                            Stage::Resolve {
+                                query: Some(query),
+                                since: now,
+                            },
+                            false,
+                        )
+                    }
+                    Err(_) if now.saturating_sub(since) <= timeout => (Stage::Resolve { query: None, since }, false),
+                    Err(_) => fail(job, sockets, None, "Net: DNS Resolution Failed", now),
+                };
+            };
+            match dns_socket.get_query_result(query) {
+                Ok(addrs) => {
+                    for addr in addrs {
+                        if let IpAddress::Ipv4(ip) = addr {
+                            return (open_socket(job, iface, sockets, ip, now), true);
+                        }
+                    }
+                    fail(job, sockets, None, "Net: DNS Resolution Failed", now)
+                }
+                Err(dns::GetQueryResultError::Pending) if now.saturating_sub(since) <= timeout => (
+                    Stage::Resolve {
+                        query: Some(query),
+                        since,
+                    },
+                    false,
+                ),
+                Err(dns::GetQueryResultError::Pending) => {
+                    dns_socket.cancel_query(query);
+                    fail(job, sockets, None, "Net: DNS Resolution Timeout", now)
+                }
+                Err(_) => fail(job, sockets, None, "Net: DNS Resolution Failed", now),
+            }
+        }
+
+        Stage::Connect { handle, since } => {
+            let socket = sockets.get_mut::<tcp::Socket>(handle);
+            if socket.may_send() {
+                let Some(target) = job.target.as_ref() else {
+                    return fail(job, sockets, Some(handle), "Net: Connect failed", now);
+                };
+                if !target.is_https || target.use_https_proxy {
+                    let head = request_head(job);
+                    return (
+                        Stage::Send {
+                            conn: Conn::plain(handle, 0),
+                            out: Outgoing::Head(head),
+                            offset: 0,
+                            since: now,
+                        },
+                        true,
+                    );
+                }
+                return match tls::TlsConnection::new_http11(target.host.as_str()) {
+                    Some(tls) => (
+                        Stage::Handshake {
+                            handle,
+                            tls: Box::new(tls),
+                            since: now,
+                        },
+                        true,
+                    ),
+                    None => fail(job, sockets, Some(handle), "Net: TLS init failed", now),
+                };
+            }
+            if !socket.is_active() {
+                return fail(job, sockets, Some(handle), "Net: Connect failed", now);
+            }
+            if now.saturating_sub(since) > timeout {
+                return fail(job, sockets, Some(handle), "Net: Connect Timeout", now);
+            }
+            (Stage::Connect { handle, since }, false)
+        }
+
+        Stage::Handshake { handle, mut tls, since } => {
+            let socket = sockets.get_mut::<tcp::Socket>(handle);
+            if !socket.is_active() {
+                return fail(job, sockets, Some(handle), "Net: TLS Handshake Failed.", now);
+            }
+            match tls.process_handshake(socket) {
+                tls::HandshakeStatus::Done => {
+                    let head = request_head(job);
+                    let conn = Conn {
+                        handle,
+                        tls: Some(tls),
+                        served: 0,
+                        early: Vec::new(),
+                    };
+                    (
+                        Stage::Send {
+                            conn,
+                            out: Outgoing::Head(head),
+                            offset: 0,
+                            since: now,
+                        },
+                        true,
+                    )
+                }
+                tls::HandshakeStatus::Error => fail(job, sockets, Some(handle), "Net: TLS Handshake Failed.", now),
+                tls::HandshakeStatus::InProgress if now.saturating_sub(since) > timeout => {
+                    fail(job, sockets, Some(handle), "Net: TLS Handshake Timeout", now)
+                }
+                tls::HandshakeStatus::InProgress => (Stage::Handshake { handle, tls, since }, false),
+            }
+        }
+
+        Stage::Send {
+            mut conn,
+            out,
+            mut offset,
+            mut since,
+        } => {
+            let socket = sockets.get_mut::<tcp::Socket>(conn.handle);
+            if !socket.may_send() {
+                return fail(job, sockets, Some(conn.handle), "Net: HTTP send failed.", now);
+            }
+            let data: &[u8] = match &out {
+                Outgoing::Head(head) => head.as_slice(),
+                Outgoing::Body => job.request.body.as_slice(),
+            };
+            let len = data.len();
+            let n = if offset < len {
+                super::tcp_send_some(socket, conn.tls.as_deref_mut(), &data[offset..])
+            } else {
+                0
+            };
+            if n > 0 {
+                offset += n;
+                since = now;
+                job.sent = true;
+            }
+            if offset < len {
+                if now.saturating_sub(since) > timeout {
+                    return fail(job, sockets, Some(conn.handle), "Net: HTTP send timeout.", now);
+                }
+                return (
+                    Stage::Send {
+                        conn,
+                        out,
+                        offset,
+                        since,
+                    },
+                    n > 0,
+                );
+            }
+            match out {
+                Outgoing::Head(_) if !job.request.body.is_empty() && job.request.expects_continue() => {
+                    (Stage::AwaitContinue { conn, since: now }, true)
+                }
+                Outgoing::Head(_) if !job.request.body.is_empty() => (
+                    Stage::Send {
+                        conn,
+                        out: Outgoing::Body,
+                        offset: 0,
+                        since: now,
+                    },
+                    true,
+                ),
+                _ => {
+                    let early = core::mem::take(&mut conn.early);
+                    let reader = Http1Reader::new(job.request.method == HttpMethod::Head, early);
+                    (
+                        Stage::Receive {
+                            conn,
+                            reader,
+                            body_sent: true,
+                            last_data: now,
+                        },
+                        true,
+                    )
+                }
+            }
+        }
+
+        Stage::AwaitContinue { mut conn, since } => {
+            let (read, open) = read_some(sockets, &mut conn);
+            conn.early.extend_from_slice(read_bytes(read));
+            let waited = now.saturating_sub(since) >= super::ms_to_ticks(EXPECT_CONTINUE_WAIT_MS);
+            let head_in = find_http_header_end(conn.early.as_slice()).is_some();
+            if !head_in && open && !waited {
+                return (Stage::AwaitContinue { conn, since }, false);
+            }
+            let interim = interim_responses_len(conn.early.as_slice());
+            if interim > 0 || !head_in {
+                // `100 Continue`, or a silent server: send the body.
+                conn.early.drain(..interim);
+                return (
+                    Stage::Send {
+                        conn,
+                        out: Outgoing::Body,
+                        offset: 0,
+                        since: now,
+                    },
+                    true,
+                );
+            }
+            // A final response before the body: the server refused it.
+            println("Net: server answered before the request body; body not sent.");
+            let early = core::mem::take(&mut conn.early);
+            let reader = Http1Reader::new(job.request.method == HttpMethod::Head, early);
+            (
+                Stage::Receive {
+                    conn,
+                    reader,
+                    body_sent: false,
+                    last_data: now,
+                },
+                true,
+            )
+        }
+
+        Stage::Receive {
+            mut conn,
+            mut reader,
+            body_sent,
+            mut last_data,
+        } => {
+            let (read, open) = read_some(sockets, &mut conn);
+            if read > 0 {
+                reader.push(read_bytes(read));
+                last_data = now;
+            }
+            let idle = read == 0 && now.saturating_sub(last_data) > timeout;
+            if !reader.is_complete() && open && !idle {
+                return (
+                    Stage::Receive {
+                        conn,
+                        reader,
+                        body_sent,
+                        last_data,
+                    },
+                    read > 0,
+                );
+            }
+            if idle {
+                println("Net: HTTP read timeout.");
+            }
+            let Some((response, reusable)) = reader.finish() else {
+                sockets.remove(conn.handle);
+                return (finish_attempt(job, None, now), true);
+            };
+            // After a refused body the server may still be reading it.
+            if reusable && body_sent && conn.tls.is_none() {
+                if let Some(target) = job.target.as_ref() {
+                    let origin = pool::Origin::new(
+                        target.host.as_str(),
+                        target.port,
+                        target.is_https,
+                        target.use_https_proxy,
+                    );
+                    pool::store(
+                        sockets,
+                        conn.handle,
+                        origin,
+                        pool::PooledConn::Http1,
+                        conn.served.saturating_add(1),
+                        now,
+                    );
+                } else {
+                    sockets.remove(conn.handle);
+                }
+            } else {
+                sockets.remove(conn.handle);
+            }
+            (finish_attempt(job, Some(response), now), true)
+        }
+
+        done @ Stage::Done { .. } => (done, false),
+    }
+}
+
+impl Conn {
+    fn plain(handle: SocketHandle, served: u32) -> Self {
+        Self {
+            handle,
+            tls: None,
+            served,
+            early: Vec::new(),
+        }
+    }
+}
+
+/// Start an attempt: resolve the target and take a pooled socket if the
+/// request may go out on one.
+fn begin_attempt(job: &mut Job, sockets: &mut SocketSet<'static>, now: u64) -> Stage {
+    job.sent = false;
+    job.target = super::http_target(&job.request);
+    let Some(target) = job.target.as_ref() else {
+        return finish_attempt(job, None, now);
+    };
+    let direct_tls = target.is_https && !target.use_https_proxy;
+    if direct_tls || !job.request.method.is_idempotent() {
+        return Stage::Resolve {
+            query: None,
+            since: now,
+        };
+    }
+    let origin = pool::Origin::new(
+        target.host.as_str(),
+        target.port,
+        target.is_https,
+        target.use_https_proxy,
+    );
+    let Some(checkout) = pool::take(sockets, &origin, true, now) else {
+        return Stage::Resolve {
+            query: None,
+            since: now,
+        };
+    };
+    match checkout.conn {
+        pool::PooledConn::Http1 => {
+            println("Net: HTTP keep-alive socket reused.");
+            job.request
+                .options
+                .apply(sockets.get_mut::<tcp::Socket>(checkout.handle));
+            let head = request_head(job);
+            Stage::Send {
+                conn: Conn::plain(checkout.handle, checkout.requests),
+                out: Outgoing::Head(head),
+                offset: 0,
+                since: now,
+            }
+        }
+        pool::PooledConn::Http2(_) => {
+            sockets.remove(checkout.handle);
+            Stage::Resolve {
+                query: None,
+                since: now,
+            }
+        }
+    }
+}
+
+fn open_socket(
+    job: &mut Job,
+    iface: &mut Interface,
+    sockets: &mut SocketSet<'static>,
+    ip: Ipv4Address,
+    now: u64,
+) -> Stage {
+    let port = job.target.as_ref().map(|t| t.port).unwrap_or(80);
+    match super::tcp_open_socket(iface, sockets, ip, port, &job.request.options) {
+        Some(handle) => Stage::Connect { handle, since: now },
+        None => finish_attempt(job, None, now),
+    }
+}
+
+fn request_head(job: &Job) -> Vec<u8> {
+    let Some(t) = job.target.as_ref() else {
+        return Vec::new();
+    };
+    println(
+        alloc::format!(
+            "Net: Sending {} {} (background)...",
+            job.request.method.as_str(),
+            t.path
+        )
+        .as_str(),
+    );
+    super::http1_request_head(
+        &job.request,
+        t.host.as_str(),
+        t.port,
+        t.path.as_str(),
+        t.is_https,
+        t.use_https_proxy,
+        &t.hints,
+    )
+    .into_bytes()
+}
+
+/// Read what the socket has into the shared buffer; returns the byte count
+/// and whether more may still arrive.
+fn read_some(sockets: &mut SocketSet<'static>, conn: &mut Conn) -> (usize, bool) {
+    let socket = sockets.get_mut::<tcp::Socket>(conn.handle);
+    // SAFETY: kernel thread; the previous `read_bytes` slice is already consumed.
+    let buf = unsafe { TLS_READ_BUF.get_mut() };
+    let read = match conn.tls.as_deref_mut() {
+        Some(tls) => tls.read(socket, buf),
+        None if socket.can_recv() => socket.recv_slice(buf).unwrap_or(0),
+        None => 0,
+    };
+    (read, socket.may_recv() || read > 0)
+}
+
+fn read_bytes(len: usize) -> &'static [u8] {
+    // SAFETY: kernel thread; no `read_some` runs while the slice is in use.
+    unsafe { &TLS_READ_BUF.get()[..len] }
+}
+
+fn fail(
+    job: &mut Job,
+    sockets: &mut SocketSet<'static>,
+    handle: Option<SocketHandle>,
+    reason: &str,
+    now: u64,
+) -> (Stage, bool) {
+    println(reason);
+    if let Some(handle) = handle {
+        sockets.remove(handle);
+    }
+    (finish_attempt(job, None, now), true)
+}
+
+/// The attempt ended with `response` (`None` on failure): back off and
+/// retry, follow a redirect, or finish.
+fn finish_attempt(job: &mut Job, response: Option<Vec<u8>>, now: u64) -> Stage {
+    let response = match (response, job.target.take()) {
+        (Some(bytes), Some(t)) => Some(super::http_postprocess_response(
+            t.effective_url.as_str(),
+            t.host.as_str(),
+            t.path.as_str(),
+            t.is_https && !t.use_https_proxy,
+            job.request.method == HttpMethod::Get,
+            bytes,
+            now,
+        )),
+        (response, _) => response,
+    };
+    if let Some(backoff) = job.request.retry_after(job.attempt, response.as_deref(), job.sent) {
+        job.attempt += 1;
+        return Stage::Waiting { until: now + super::ms_to_ticks(backoff) };
+    }
+    if let Some(next) = response
+        .as_deref()
+        .and_then(|bytes| job.request.next_hop(bytes, job.hops))
+    {
+        job.request = next;
+        job.hops += 1;
+        job.attempt = 0;
+        return Stage::Waiting { until: now };
+    }
+    {
+        let mut stats = WORKER_STATS.lock();
+        if response.is_some() {
+            stats.completed += 1;
+        } else {
+            stats.failed += 1;
+        }
+    }
+    crate::replay::record_reply(job.replay_key.as_str(), response.as_deref());
+    Stage::Done { response, at: now }
+}
+
+/// Shared implementation of `net jobs [get <url>|cancel <id>]`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let args = args.trim();
+    let mut parts = args.split_whitespace();
+    match parts.next() {
+        Some(sub) if sub.eq_ignore_ascii_case("get") => {
+            let (Some(url), None) = (parts.next(), parts.next()) else {
+                out.push(String::from("Uso: net jobs get <url>"));
+                return out;
+            };
+            let submitted = submit_with(HttpRequest::get(url), |id, response| {
+                let status = response
+                    .as_deref()
+                    .and_then(|bytes| redux_netparse::http::parse_http_headers(bytes).status_code);
+                match (status, response.as_ref()) {
+                    (Some(status), Some(bytes)) => println(
+                        alloc::format!("net jobs: #{} terminada, HTTP {} ({} bytes)", id, status, bytes.len()).as_str(),
+                    ),
+                    _ => println(alloc::format!("net jobs: #{} fallo", id).as_str()),
+                }
+            });
+            match submitted {
+                Some(id) => out.push(alloc::format!("net jobs: #{} en segundo plano -> {}", id, url)),
+                None => out.push(String::from("net jobs: no se pudo encolar la peticion")),
+            }
+        }
+        Some(sub) if sub.eq_ignore_ascii_case("cancel") => {
+            match parts
+                .next()
+                .and_then(|id| id.trim_start_matches('#').parse::<RequestId>().ok())
+            {
+                Some(id) if cancel(id) => out.push(alloc::format!("net jobs: #{} cancelada", id)),
+                Some(id) => out.push(alloc::format!("net jobs: #{} no existe", id)),
+                None => out.push(String::from("Uso: net jobs cancel <id>")),
+            }
+        }
+        Some(_) => out.push(String::from("Uso: net jobs [get <url>|cancel <id>]")),
+        None => {
+            let now = crate::timer::ticks();
+            let stats = *WORKER_STATS.lock();
+            // SAFETY: kernel thread, and no other job borrow is alive here.
+            let jobs = unsafe { JOBS.get() };
+            out.push(alloc::format!(
+                "net jobs: {} peticiones en segundo plano (max {}, {} a la vez)",
+                jobs.len(),
+                WORKER_MAX_JOBS,
+                WORKER_MAX_ACTIVE
+            ));
+            for job in jobs.iter() {
+                let age = now.saturating_sub(job.queued_at);
+                out.push(alloc::format!(
+                    "  #{}  {} {}  {}, intento {}, {}.{} s",
+                    job.id,
+                    job.request.method.as_str(),
+                    job.request.url,
+                    job.stage.label(),
+                    job.attempt + 1,
+                    age / 100,
+                    (age % 100) / 10
+                ));
+            }
+            out.push(alloc::format!(
+                "  Enviadas: {}, completadas: {}, fallidas: {}, canceladas: {}",
+                stats.submitted,
+                stats.completed,
+                stats.failed,
+                stats.cancelled
+            ));
+        }
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "net_worker";
+
+    fn unknown_ids_are_ready_and_not_cancellable() {
+        crate::selftest::ensure(matches!(poll(RequestId::MAX), Poll::Ready(None)), "poll de id desconocido")?;
+        crate::selftest::ensure(!cancel(RequestId::MAX), "cancel de id desconocido")
+    }
+
+    fn only_network_stages_count_as_active() {
+        crate::selftest::ensure(!Stage::Waiting { until: 0 }.is_active(), "en cola")?;
+        crate::selftest::ensure(!Stage::Done { response: None, at: 0 }.is_active(), "terminada")?;
+        crate::selftest::ensure(Stage::Resolve { query: None, since: 0 }.is_active(), "dns")?;
+        crate::selftest::ensure_eq(Stage::Resolve { query: None, since: 0 }.label(), "dns", "etiqueta")
+    }
+}
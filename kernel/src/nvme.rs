@@ -1,4 +1,4 @@
-use crate::pci::{PciDevice, read_bar};
+use crate::pci::{PciDevice, PciDriver, PciMatch, read_bar};
 use crate::println;
 use crate::memory;
 
@@ -23,8 +23,12 @@ const CC_EN: u32 = 1 << 0;
 const CC_IOSQES: u32 = 6 << 16;  // I/O Submission Queue Entry Size (2^6 = 64 bytes)
 const CC_IOCQES: u32 = 4 << 20;  // I/O Completion Queue Entry Size (2^4 = 16 bytes)
 
+const CC_SHN_NORMAL: u32 = 1 << 14; // Shutdown Notification: normal shutdown
+
 // Controller Status bits
 const CSTS_RDY: u32 = 1 << 0;
+const CSTS_SHST_MASK: u32 = 3 << 2;
+const CSTS_SHST_COMPLETE: u32 = 2 << 2;
 
 #[repr(C)]
 #[derive(Copy, Clone)]
@@ -89,6 +93,78 @@ impl NvmeController {
         false
     }
 
+    /// Resets the controller and creates the admin and I/O queue pairs.
+    /// Runs at probe and again on resume, when the controller has lost them.
+    unsafe fn bring_up(&mut self) -> Result<(), &'static str> {
+        // Completions are matched by command id alone; fill the CQs with an id
+        // never issued so an entry left from before a resume cannot match.
+        core::ptr::write_bytes(self.admin_sq as *mut u8, 0, 4096);
+        core::ptr::write_bytes(self.io_sq as *mut u8, 0, 4096);
+        core::ptr::write_bytes(self.admin_cq as *mut u8, 0xFF, 4096);
+        core::ptr::write_bytes(self.io_cq as *mut u8, 0xFF, 4096);
+        self.admin_sq_tail = 0;
+        self.io_sq_tail = 0;
+
+        // 1. Disable controller
+        self.write_reg(REG_CC, 0);
+        if !self.wait_ready(false, 5000) {
+            return Err("NVMe: Controller disable timeout");
+        }
+
+        // 2. Configure admin queues
+        self.write_reg(REG_AQA, 0x003F003F); // 64 entries each
+        self.write_reg(REG_ASQ, (self.admin_sq as u64 & 0xFFFFFFFF) as u32);
+        self.write_reg(REG_ASQ + 4, ((self.admin_sq as u64) >> 32) as u32);
+        self.write_reg(REG_ACQ, (self.admin_cq as u64 & 0xFFFFFFFF) as u32);
+        self.write_reg(REG_ACQ + 4, ((self.admin_cq as u64) >> 32) as u32);
+
+        // 3. Enable controller
+        self.write_reg(REG_CC, CC_EN | CC_IOSQES | CC_IOCQES);
+        if !self.wait_ready(true, 5000) {
+            return Err("NVMe: Controller enable timeout");
+        }
+
+        // 4. Create I/O Completion Queue
+        let mut cmd: NvmeCommand = core::mem::zeroed();
+        cmd.opcode = NVME_CMD_CREATE_IO_CQ;
+        cmd.command_id = 1;
+        cmd.prp1 = self.io_cq as u64;
+        cmd.cdw10 = (1 << 16) | 63; // QID=1, Size=64
+        cmd.cdw11 = 1; // Physically contiguous
+
+        if !self.submit_admin_cmd(cmd) {
+            return Err("NVMe: Failed to create I/O CQ");
+        }
+
+        // 5. Create I/O Submission Queue
+        cmd = core::mem::zeroed();
+        cmd.opcode = NVME_CMD_CREATE_IO_SQ;
+        cmd.command_id = 2;
+        cmd.prp1 = self.io_sq as u64;
+        cmd.cdw10 = (1 << 16) | 63; // QID=1, Size=64
+        cmd.cdw11 = (1 << 16) | 1; // CQID=1, Physically contiguous
+
+        if !self.submit_admin_cmd(cmd) {
+            return Err("NVMe: Failed to create I/O SQ");
+        }
+
+        Ok(())
+    }
+
+    /// Normal shutdown notification: the controller flushes its write cache
+    /// and reports when it is safe to lose power.
+    unsafe fn shutdown(&self) -> bool {
+        let cc = self.read_reg(REG_CC);
+        self.write_reg(REG_CC, (cc & !(3 << 14)) | CC_SHN_NORMAL);
+        for _ in 0..(5000 * 10) {
+            if self.read_reg(REG_CSTS) & CSTS_SHST_MASK == CSTS_SHST_COMPLETE {
+                return true;
+            }
+            uefi::boot::stall(100);
+        }
+        false
+    }
+
     unsafe fn submit_admin_cmd(&mut self, cmd: NvmeCommand) -> bool {
         // Write command to submission queue
         core::ptr::write_volatile(self.admin_sq.add(self.admin_sq_tail as usize), cmd);
@@ -144,6 +220,45 @@ impl NvmeController {
     }
 }
 
+pub static PCI_DRIVER: PciDriver = PciDriver {
+    name: "nvme",
+    ids: &[PciMatch::class(0x01, 0x08)],
+    probe: init,
+};
+
+static PM_OPS: crate::pm::PmOps = crate::pm::PmOps {
+    name: "nvme",
+    suspend,
+    resume,
+};
+
+fn suspend() -> Result<(), &'static str> {
+    unsafe {
+        if let Some(ctrl) = &NVME_CONTROLLER {
+            if !ctrl.shutdown() {
+                return Err("la controladora no confirmo el apagado");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// After shutdown (and S3) the queues are gone; build them again over the
+/// same memory.
+fn resume() {
+    unsafe {
+        if let Some(ctrl) = &mut NVME_CONTROLLER {
+            if let Err(err) = ctrl.bring_up() {
+                println(err);
+            }
+        }
+    }
+}
+
+pub fn is_ready() -> bool {
+    unsafe { NVME_CONTROLLER.is_some() }
+}
+
 pub fn init(device: PciDevice) {
     unsafe {
         let bar0 = read_bar(device.bus, device.slot, device.func, 0);
@@ -169,54 +284,14 @@ pub fn init(device: PciDevice) {
                 data_buffer,
             };
 
-            // 1. Disable controller
-            ctrl.write_reg(REG_CC, 0);
-            if !ctrl.wait_ready(false, 5000) {
-                println("NVMe: Controller disable timeout");
-                return;
-            }
-
-            // 2. Configure admin queues
-            ctrl.write_reg(REG_AQA, 0x003F003F); // 64 entries each
-            ctrl.write_reg(REG_ASQ, (admin_sq as u64 & 0xFFFFFFFF) as u32);
-            ctrl.write_reg(REG_ASQ + 4, ((admin_sq as u64) >> 32) as u32);
-            ctrl.write_reg(REG_ACQ, (admin_cq as u64 & 0xFFFFFFFF) as u32);
-            ctrl.write_reg(REG_ACQ + 4, ((admin_cq as u64) >> 32) as u32);
-
-            // 3. Enable controller
-            ctrl.write_reg(REG_CC, CC_EN | CC_IOSQES | CC_IOCQES);
-            if !ctrl.wait_ready(true, 5000) {
-                println("NVMe: Controller enable timeout");
-                return;
-            }
-
-            // 4. Create I/O Completion Queue
-            let mut cmd: NvmeCommand = core::mem::zeroed();
-            cmd.opcode = NVME_CMD_CREATE_IO_CQ;
-            cmd.command_id = 1;
-            cmd.prp1 = io_cq as u64;
-            cmd.cdw10 = (1 << 16) | 63; // QID=1, Size=64
-            cmd.cdw11 = 1; // Physically contiguous
-
-            if !ctrl.submit_admin_cmd(cmd) {
-                println("NVMe: Failed to create I/O CQ");
-                return;
-            }
-
-            // 5. Create I/O Submission Queue
-            cmd = core::mem::zeroed();
-            cmd.opcode = NVME_CMD_CREATE_IO_SQ;
-            cmd.command_id = 2;
-            cmd.prp1 = io_sq as u64;
-            cmd.cdw10 = (1 << 16) | 63; // QID=1, Size=64
-            cmd.cdw11 = (1 << 16) | 1; // CQID=1, Physically contiguous
-
-            if !ctrl.submit_admin_cmd(cmd) {
-                println("NVMe: Failed to create I/O SQ");
+            if let Err(err) = ctrl.bring_up() {
+                println(err);
                 return;
             }
 
             NVME_CONTROLLER = Some(ctrl);
+            crate::pm::register(&PM_OPS);
+            crate::device::add_class_device(Some(crate::device::pci_function(&device)), "nvme", "block", "nvme");
             println("NVMe: Initialized successfully");
         } else {
             println("NVMe: Failed to find BAR0.");
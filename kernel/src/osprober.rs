@@ -0,0 +1,701 @@
+//! os-prober for the GRUB menu: finds the other systems on the volumes the
+//! firmware can read and turns each into a menuentry.
+//!
+//! Only FAT volumes are visible: ESPs, and /boot when it is FAT (XBOOTLDR).
+//! On each one it reads:
+//! - boot loader spec entries in `\loader\entries\*.conf` and unified kernel
+//!   images in `\EFI\Linux`;
+//! - GRUB configs in `\boot\grub`, `\grub` and `\EFI\<distro>`. Their
+//!   top-level menuentries are copied as they are. A stub that only jumps to
+//!   the real config on the root filesystem, which is what Ubuntu or Fedora
+//!   leave on the ESP, becomes one entry that chainloads the distribution's
+//!   shim or GRUB;
+//! - `vmlinuz-<version>` in `\` and `\boot` that nothing above boots yet, with
+//!   its initrd. The root filesystem is unknown from here, so the entry
+//!   passes the `linux.cmdline` setting (`ro` unless set; see `linuxboot`);
+//! - Windows Boot Manager.
+//!
+//! Zenox volumes and the configs Zenox generated are skipped. `bootmenu`
+//! lists what this finds and boots it itself; `sync` runs with it and
+//! rewrites a config Zenox generated for GRUB on older installs only when
+//! the menu it would write differs from the one on disk.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use uefi::boot;
+use uefi::fs::FileSystem as UefiFileSystem;
+use uefi::proto::media::fs::SimpleFileSystem;
+use uefi::{CString16, Handle};
+
+/// First line of every config `crate::build_forced_grub_config_payload` writes.
+pub const GENERATED_MARK: &str = "# Generado por Zenox OS";
+/// Loader the generated Zenox entry boots first.
+pub const ZENOX_LOADER: &str = "/EFI/BOOT/REDUX64.EFI";
+
+/// How GRUB boots a target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Boot {
+    /// Chainload an EFI image.
+    Chain(String),
+    /// Load a kernel and its initrds.
+    Linux {
+        kernel: String,
+        initrds: Vec<String>,
+        options: String,
+    },
+    /// Body of a menuentry copied from another GRUB config.
+    Menu(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Target {
+    pub title: String,
+    pub boot: Boot,
+}
+
+impl Target {
+    fn new(title: &str, boot: Boot) -> Self {
+        Self {
+            title: String::from(title),
+            boot,
+        }
+    }
+
+    /// The kernel or image it boots, for the list and for dedup.
+    pub fn image(&self) -> &str {
+        match &self.boot {
+            Boot::Chain(path) => path.as_str(),
+            Boot::Linux { kernel, .. } => kernel.as_str(),
+            Boot::Menu(_) => "",
+        }
+    }
+}
+
+/// A volume path as GRUB writes it: forward slashes, rooted.
+fn grub_path(path: &str) -> String {
+    let path = path.trim().replace('\\', "/");
+    if path.starts_with('/') {
+        path
+    } else {
+        alloc::format!("/{}", path)
+    }
+}
+
+/// A GRUB word in single quotes.
+fn quote(text: &str) -> String {
+    alloc::format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// Whether Zenox wrote this config.
+pub fn is_generated(text: &str) -> bool {
+    text.starts_with(GENERATED_MARK) || text.contains("--set=reduxroot")
+}
+
+/// The menuentry for a target, CRLF like the rest of the generated config.
+pub fn menuentry(target: &Target) -> String {
+    let mut out = alloc::format!("menuentry {} {{\r\n", quote(target.title.as_str()));
+    match &target.boot {
+        Boot::Chain(path) => {
+            let _ = write!(
+                out,
+                "    if search --no-floppy --file --set=osroot {0}; then\r\n        chainloader ($osroot){0}\r\n        boot\r\n    fi\r\n",
+                path
+            );
+        }
+        Boot::Linux {
+            kernel,
+            initrds,
+            options,
+        } => {
+            let _ = write!(
+                out,
+                "    if search --no-floppy --file --set=osroot {0}; then\r\n        linux ($osroot){0}",
+                kernel
+            );
+            if !options.is_empty() {
+                out.push(' ');
+                out.push_str(options.as_str());
+            }
+            out.push_str("\r\n");
+            if !initrds.is_empty() {
+                out.push_str("        initrd");
+                for initrd in initrds {
+                    let _ = write!(out, " ($osroot){}", initrd);
+                }
+                out.push_str("\r\n");
+            }
+            out.push_str("        boot\r\n    fi\r\n");
+        }
+        Boot::Menu(body) => {
+            for line in body.lines() {
+                let _ = write!(out, "    {}\r\n", line);
+            }
+        }
+    }
+    out.push_str("}\r\n");
+    out
+}
+
+/// A boot loader spec entry (`\loader\entries\*.conf`).
+pub fn parse_loader_entry(text: &str) -> Option<Target> {
+    let mut title = "";
+    let mut version = "";
+    let mut linux = None;
+    let mut efi = None;
+    let mut initrds = Vec::new();
+    let mut options = String::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let value = value.trim();
+        match key {
+            "title" => title = value,
+            "version" => version = value,
+            "linux" => linux = Some(grub_path(value)),
+            "efi" => efi = Some(grub_path(value)),
+            "initrd" => initrds.push(grub_path(value)),
+            "options" => {
+                if !options.is_empty() {
+                    options.push(' ');
+                }
+                options.push_str(value);
+            }
+            _ => {}
+        }
+    }
+    let boot = match (linux, efi) {
+        (Some(kernel), _) => Boot::Linux {
+            kernel,
+            initrds,
+            options,
+        },
+        (None, Some(path)) => Boot::Chain(path),
+        (None, None) => return None,
+    };
+    let mut name = String::from(if title.is_empty() { "Linux" } else { title });
+    if !version.is_empty() && !name.contains(version) {
+        let _ = write!(name, " ({})", version);
+    }
+    Some(Target { title: name, boot })
+}
+
+/// Net `{` minus `}` on a line, outside quotes and comments.
+fn brace_delta(line: &str) -> i32 {
+    let mut delta = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for ch in line.chars() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (quote, ch) {
+            (Some('"'), '\\') => escaped = true,
+            (Some(open), _) if ch == open => quote = None,
+            (Some(_), _) => {}
+            (None, '\\') => escaped = true,
+            (None, '\'' | '"') => quote = Some(ch),
+            (None, '#') => break,
+            (None, '{') => delta += 1,
+            (None, '}') => delta -= 1,
+            _ => {}
+        }
+    }
+    delta
+}
+
+/// The first argument of a `menuentry` line: its title.
+fn menuentry_title(rest: &str) -> String {
+    let rest = rest.trim_start();
+    let mut chars = rest.chars();
+    match chars.next() {
+        Some(open @ ('\'' | '"')) => chars.take_while(|ch| *ch != open).collect(),
+        _ => rest
+            .split(|ch: char| ch.is_whitespace() || ch == '{')
+            .next()
+            .map(String::from)
+            .unwrap_or_default(),
+    }
+}
+
+/// Top-level menuentries of a GRUB config. Submenus (the "advanced options"
+/// of most distributions) are left out, and so are calls to functions the
+/// config defines, since those do not exist in the generated one.
+pub fn parse_grub_cfg(text: &str) -> Vec<Target> {
+    let mut functions: Vec<&str> = Vec::new();
+    for line in text.lines() {
+        if let Some(rest) = line.trim().strip_prefix("function ") {
+            if let Some(name) = rest.split(|ch: char| ch.is_whitespace() || ch == '{').next() {
+                functions.push(name);
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut depth = 0i32;
+    let mut current: Option<(String, String)> = None;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        let delta = brace_delta(trimmed);
+        if depth == 0 && current.is_none() {
+            if let Some(rest) = trimmed.strip_prefix("menuentry ") {
+                if delta > 0 {
+                    current = Some((menuentry_title(rest), String::new()));
+                }
+            }
+        } else if let Some((_, body)) = current.as_mut() {
+            let closes = depth + delta <= 0;
+            let command = trimmed.split_whitespace().next().unwrap_or("");
+            if !closes && !trimmed.is_empty() && !trimmed.starts_with('#') && !functions.contains(&command) {
+                body.push_str(trimmed);
+                body.push('\n');
+            }
+        }
+        depth = (depth + delta).max(0);
+        if depth == 0 {
+            if let Some((title, body)) = current.take() {
+                if !title.is_empty() && !body.is_empty() {
+                    out.push(Target {
+                        title,
+                        boot: Boot::Menu(body),
+                    });
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Whether a GRUB config only hands over to another one (`configfile`).
+pub fn is_stub(text: &str) -> bool {
+    parse_grub_cfg(text).is_empty() && text.contains("configfile")
+}
+
+/// Menu title for a `\EFI\<dir>` vendor directory.
+pub fn distro_title(dir: &str) -> String {
+    let known = [
+        ("ubuntu", "Ubuntu"),
+        ("debian", "Debian GNU/Linux"),
+        ("fedora", "Fedora Linux"),
+        ("opensuse", "openSUSE"),
+        ("sles", "SUSE Linux Enterprise"),
+        ("arch", "Arch Linux"),
+        ("manjaro", "Manjaro Linux"),
+        ("centos", "CentOS"),
+        ("rocky", "Rocky Linux"),
+        ("almalinux", "AlmaLinux"),
+        ("redhat", "Red Hat Enterprise Linux"),
+        ("pop", "Pop!_OS"),
+        ("neon", "KDE neon"),
+        ("kali", "Kali Linux"),
+        ("gentoo", "Gentoo Linux"),
+    ];
+    if let Some((_, title)) = known.iter().find(|(name, _)| name.eq_ignore_ascii_case(dir)) {
+        return String::from(*title);
+    }
+    let mut chars = dir.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::from("Linux"),
+    }
+}
+
+/// `vmlinuz-<version>` kernels among the files of `dir`, each with the
+/// initrd of the same version when there is one, and a bare `vmlinuz` with
+/// `initrd.img`. All boot with `options`.
+pub fn kernel_targets(dir: &str, files: &[String], options: &str) -> Vec<Target> {
+    let find = |wanted: &str| files.iter().find(|name| name.eq_ignore_ascii_case(wanted));
+    let mut out = Vec::new();
+    for name in files {
+        let version = if name.eq_ignore_ascii_case("vmlinuz") {
+            ""
+        } else {
+            match name.get(8..).filter(|_| name[..8].eq_ignore_ascii_case("vmlinuz-")) {
+                Some(version) if !version.is_empty() => version,
+                _ => continue,
+            }
+        };
+        if version.to_ascii_lowercase().ends_with(".efi") {
+            continue;
+        }
+        let candidates = if version.is_empty() {
+            [
+                String::from("initrd.img"),
+                String::from("initramfs.img"),
+                String::from("initrd"),
+            ]
+        } else {
+            [
+                alloc::format!("initrd.img-{}", version),
+                alloc::format!("initramfs-{}.img", version),
+                alloc::format!("initrd-{}", version),
+            ]
+        };
+        let initrds = candidates
+            .iter()
+            .find_map(|wanted| find(wanted.as_str()))
+            .map(|initrd| alloc::vec![grub_path(alloc::format!("{}\\{}", dir, initrd).as_str())])
+            .unwrap_or_default();
+        out.push(Target {
+            title: if version.is_empty() {
+                String::from("Linux")
+            } else {
+                alloc::format!("Linux {}", version)
+            },
+            boot: Boot::Linux {
+                kernel: grub_path(alloc::format!("{}\\{}", dir, name).as_str()),
+                initrds,
+                options: String::from(options.trim()),
+            },
+        });
+    }
+    out.sort_by(|a, b| b.title.cmp(&a.title));
+    out
+}
+
+/// Add `new` unless a target already boots the same thing.
+fn push_unique(out: &mut Vec<Target>, new: Target) {
+    let seen = out.iter().any(|target| {
+        target.boot == new.boot || (!new.image().is_empty() && target.image().eq_ignore_ascii_case(new.image()))
+    });
+    if !seen {
+        out.push(new);
+    }
+}
+
+fn read_text(fs: &mut UefiFileSystem, path: &str) -> Option<String> {
+    let path = CString16::try_from(path).ok()?;
+    let bytes = fs.read(path.as_ref()).ok()?;
+    Some(String::from_utf8_lossy(bytes.as_slice()).into_owned())
+}
+
+/// Names in a directory, as (name, is_directory).
+fn list_dir(fs: &mut UefiFileSystem, dir: &str) -> Vec<(String, bool)> {
+    let Ok(path) = CString16::try_from(dir) else {
+        return Vec::new();
+    };
+    let Ok(iter) = fs.read_dir(path.as_ref()) else {
+        return Vec::new();
+    };
+    iter.filter_map(|entry| entry.ok())
+        .map(|info| (String::from(info.file_name()), info.is_directory()))
+        .filter(|(name, _)| name != "." && name != "..")
+        .collect()
+}
+
+pub(crate) fn files_in(fs: &mut UefiFileSystem, dir: &str) -> Vec<String> {
+    list_dir(fs, dir)
+        .into_iter()
+        .filter(|(_, is_dir)| !is_dir)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Everything one volume can boot, in the order the module doc lists.
+fn probe_volume(fs: &mut UefiFileSystem, out: &mut Vec<Target>, options: &str) {
+    let mut entries = files_in(fs, "\\loader\\entries");
+    entries.sort();
+    for name in entries
+        .iter()
+        .filter(|name| name.to_ascii_lowercase().ends_with(".conf"))
+    {
+        let path = alloc::format!("\\loader\\entries\\{}", name);
+        if let Some(target) = read_text(fs, path.as_str()).as_deref().and_then(parse_loader_entry) {
+            push_unique(out, target);
+        }
+    }
+
+    for cfg in ["\\boot\\grub\\grub.cfg", "\\grub\\grub.cfg"] {
+        let Some(text) = read_text(fs, cfg) else {
+            continue;
+        };
+        if !is_generated(text.as_str()) {
+            for target in parse_grub_cfg(text.as_str()) {
+                push_unique(out, target);
+            }
+        }
+    }
+
+    // Zenox's own directories, and the ones handled elsewhere.
+    let skip = [
+        "BOOT",
+        "GRUB",
+        "ZENOX",
+        "GOOS",
+        "REDUXOS",
+        "Microsoft",
+        "systemd",
+        "Linux",
+    ];
+    let mut vendors = list_dir(fs, "\\EFI");
+    vendors.sort();
+    for (vendor, is_dir) in vendors {
+        if !is_dir || skip.iter().any(|name| name.eq_ignore_ascii_case(vendor.as_str())) {
+            continue;
+        }
+        let dir = alloc::format!("\\EFI\\{}", vendor);
+        let files = files_in(fs, dir.as_str());
+        let file = |wanted: &str| files.iter().find(|name| name.eq_ignore_ascii_case(wanted)).cloned();
+        let text = file("grub.cfg").and_then(|name| read_text(fs, alloc::format!("{}\\{}", dir, name).as_str()));
+        if let Some(text) = text.as_deref().filter(|text| !is_generated(text) && !is_stub(text)) {
+            for target in parse_grub_cfg(text) {
+                push_unique(out, target);
+            }
+            continue;
+        }
+        if let Some(loader) = file("shimx64.efi").or_else(|| file("grubx64.efi")) {
+            let path = grub_path(alloc::format!("{}\\{}", dir, loader).as_str());
+            push_unique(
+                out,
+                Target::new(distro_title(vendor.as_str()).as_str(), Boot::Chain(path)),
+            );
+        }
+    }
+
+    let mut images = files_in(fs, "\\EFI\\Linux");
+    images.sort();
+    for image in images.iter().filter(|name| name.to_ascii_lowercase().ends_with(".efi")) {
+        let title = alloc::format!("Linux ({})", &image[..image.len() - 4]);
+        let path = grub_path(alloc::format!("\\EFI\\Linux\\{}", image).as_str());
+        push_unique(out, Target::new(title.as_str(), Boot::Chain(path)));
+    }
+
+    for dir in ["", "\\boot"] {
+        let files = files_in(fs, if dir.is_empty() { "\\" } else { dir });
+        for target in kernel_targets(dir, files.as_slice(), options) {
+            push_unique(out, target);
+        }
+    }
+
+    let windows = files_in(fs, "\\EFI\\Microsoft\\Boot");
+    for loader in ["bootmgfw.redux.bak.efi", "bootmgfw.efi"] {
+        if let Some(name) = windows.iter().find(|name| name.eq_ignore_ascii_case(loader)) {
+            let path = grub_path(alloc::format!("\\EFI\\Microsoft\\Boot\\{}", name).as_str());
+            push_unique(out, Target::new("Windows Boot Manager", Boot::Chain(path)));
+            break;
+        }
+    }
+}
+
+/// Every system on the volumes the firmware sees, Zenox excluded, with the
+/// volume it is on.
+pub fn probe_handles() -> Vec<(Handle, Target)> {
+    let mut out = Vec::new();
+    let Ok(handles) = boot::find_handles::<SimpleFileSystem>() else {
+        return out;
+    };
+    let options = crate::linuxboot::command_line();
+    for handle in handles.iter().copied() {
+        if crate::handle_has_installed_redux_marker(handle) {
+            continue;
+        }
+        let Ok(proto) = boot::open_protocol_exclusive::<SimpleFileSystem>(handle) else {
+            continue;
+        };
+        let mut found = Vec::new();
+        probe_volume(&mut UefiFileSystem::new(proto), &mut found, options.as_str());
+        out.extend(found.into_iter().map(|target| (handle, target)));
+    }
+    out
+}
+
+/// The systems for the GRUB menu. `search --file` takes the first volume
+/// with the file, so one entry per image is enough.
+pub fn grub_targets(found: &[(Handle, Target)]) -> Vec<Target> {
+    let mut out = Vec::new();
+    for (_, target) in found {
+        push_unique(&mut out, target.clone());
+    }
+    out
+}
+
+pub fn probe() -> Vec<Target> {
+    grub_targets(probe_handles().as_slice())
+}
+
+/// Rewrite the generated GRUB configs on internal volumes whose menu differs
+/// from what `targets` gives. Returns how many files changed.
+pub fn sync_with(targets: &[Target]) -> usize {
+    let cfg = crate::build_forced_grub_config_payload(ZENOX_LOADER, targets);
+    let mut written = 0;
+    for handle in crate::collect_internal_simplefs_handles() {
+        let Ok(proto) = boot::open_protocol_exclusive::<SimpleFileSystem>(handle) else {
+            continue;
+        };
+        let mut fs = UefiFileSystem::new(proto);
+        for path in crate::grub_config_paths() {
+            let Ok(old) = fs.read(path) else {
+                continue;
+            };
+            let generated = core::str::from_utf8(old.as_slice()).map(is_generated).unwrap_or(false);
+            if generated && old != cfg && fs.write(path, cfg.as_slice()).is_ok() {
+                written += 1;
+            }
+        }
+    }
+    written
+}
+
+/// Bring the generated GRUB configs up to date with what `probe_handles`
+/// found. The boot menu calls this with the systems it lists.
+pub fn sync(found: &[(Handle, Target)]) {
+    let targets = grub_targets(found);
+    let written = sync_with(targets.as_slice());
+    if written > 0 {
+        crate::println(
+            alloc::format!(
+                "GRUB: menu regenerado con {} sistemas ({} archivos)",
+                targets.len() + 1,
+                written
+            )
+            .as_str(),
+        );
+    }
+}
+
+fn describe(target: &Target) -> String {
+    match &target.boot {
+        Boot::Chain(path) => alloc::format!("{} -> {}", target.title, path),
+        Boot::Linux { kernel, initrds, .. } => {
+            alloc::format!("{} -> {} ({} initrd)", target.title, kernel, initrds.len())
+        }
+        Boot::Menu(_) => alloc::format!("{} (copiada de grub.cfg)", target.title),
+    }
+}
+
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    match args.trim() {
+        "" | "list" => {
+            let targets = probe();
+            if targets.is_empty() {
+                lines.push(String::from("osprober: no se encontraron otros sistemas"));
+            }
+            for (index, target) in targets.iter().enumerate() {
+                lines.push(alloc::format!("{}) {}", index + 1, describe(target)));
+            }
+        }
+        "cfg" => {
+            let cfg = crate::build_forced_grub_config_payload(ZENOX_LOADER, probe().as_slice());
+            lines.extend(String::from_utf8_lossy(cfg.as_slice()).lines().map(String::from));
+        }
+        "update" => {
+            let targets = probe();
+            let written = sync_with(targets.as_slice());
+            lines.push(if written == 0 {
+                String::from("osprober: los grub.cfg generados ya estan al dia")
+            } else {
+                alloc::format!(
+                    "osprober: {} grub.cfg regenerados ({} sistemas)",
+                    written,
+                    targets.len() + 1
+                )
+            });
+        }
+        _ => lines.push(String::from("Uso: osprober [list|cfg|update]")),
+    }
+    lines
+}
+
+crate::selftest::kernel_tests! {
+    "osprober";
+
+    fn parses_loader_entries() {
+        let text = "title Fedora Linux\nversion 6.8.5-301.fc40.x86_64\n# comentario\n\
+                    linux /vmlinuz-6.8.5\ninitrd /intel-ucode.img\ninitrd /initramfs-6.8.5.img\n\
+                    options root=UUID=1234 ro\noptions quiet\n";
+        let target = parse_loader_entry(text).ok_or("sin entrada")?;
+        crate::selftest::ensure_eq(target.title.as_str(), "Fedora Linux (6.8.5-301.fc40.x86_64)", "titulo")?;
+        crate::selftest::ensure_eq(
+            target.boot,
+            Boot::Linux {
+                kernel: String::from("/vmlinuz-6.8.5"),
+                initrds: alloc::vec![String::from("/intel-ucode.img"), String::from("/initramfs-6.8.5.img")],
+                options: String::from("root=UUID=1234 ro quiet"),
+            },
+            "arranque",
+        )?;
+        let uki = parse_loader_entry("efi \\EFI\\Linux\\arch.efi\n").ok_or("sin efi")?;
+        crate::selftest::ensure_eq(uki.boot, Boot::Chain(String::from("/EFI/Linux/arch.efi")), "efi")?;
+        crate::selftest::ensure(parse_loader_entry("title vacia\n").is_none(), "entrada sin imagen")
+    }
+
+    fn parses_grub_menuentries() {
+        let text = "function load_video {\n  insmod all_video\n}\n\
+                    menuentry 'Ubuntu' --class ubuntu $menuentry_id_option 'gnulinux-simple' {\n\
+                    \tload_video\n\tsearch --no-floppy --fs-uuid --set=root abcd\n\
+                    \tif [ x$grub_platform = xefi ]; then set gfxpayload=keep; fi\n\
+                    \tlinux /vmlinuz root=UUID=abcd ro ${vt_handoff}\n\tinitrd /initrd.img\n}\n\
+                    submenu 'Advanced options for Ubuntu' {\n\
+                    \tmenuentry 'Ubuntu, with Linux 6.8' {\n\t\tlinux /vmlinuz-6.8\n\t}\n}\n\
+                    menuentry \"Memory test\" {\n\tlinux16 /memtest86+.bin\n}\n";
+        let targets = parse_grub_cfg(text);
+        crate::selftest::ensure_eq(targets.len(), 2, "entradas")?;
+        crate::selftest::ensure_eq(targets[0].title.as_str(), "Ubuntu", "titulo")?;
+        let Boot::Menu(body) = &targets[0].boot else {
+            return Err("sin cuerpo");
+        };
+        crate::selftest::ensure(!body.contains("load_video"), "funcion del cfg original")?;
+        crate::selftest::ensure(body.contains("linux /vmlinuz root=UUID=abcd ro ${vt_handoff}"), "linux")?;
+        crate::selftest::ensure_eq(targets[1].title.as_str(), "Memory test", "segundo titulo")?;
+        let stub = "search.fs_uuid abcd root\nset prefix=($root)'/boot/grub'\nconfigfile $prefix/grub.cfg\n";
+        crate::selftest::ensure(is_stub(stub), "stub")?;
+        crate::selftest::ensure(!is_stub(text), "cfg completo")
+    }
+
+    fn pairs_kernels_with_initrds() {
+        let files = [
+            String::from("VMLINUZ-6.1.0-18-AMD64"),
+            String::from("initrd.img-6.1.0-18-amd64"),
+            String::from("vmlinuz-6.1.0-17-amd64"),
+            String::from("config-6.1.0-18-amd64"),
+        ];
+        let targets = kernel_targets("\\boot", &files, " ro quiet ");
+        crate::selftest::ensure_eq(targets.len(), 2, "nucleos")?;
+        crate::selftest::ensure_eq(
+            targets[0].boot.clone(),
+            Boot::Linux {
+                kernel: String::from("/boot/VMLINUZ-6.1.0-18-AMD64"),
+                initrds: alloc::vec![String::from("/boot/initrd.img-6.1.0-18-amd64")],
+                options: String::from("ro quiet"),
+            },
+            "con initrd",
+        )?;
+        let Boot::Linux { initrds, .. } = &targets[1].boot else {
+            return Err("no es linux");
+        };
+        crate::selftest::ensure(initrds.is_empty(), "sin initrd")?;
+        let plain = [String::from("vmlinuz"), String::from("INITRD.IMG"), String::from("vmlinuz.efi")];
+        let targets = kernel_targets("\\EFI\\LINUX", &plain, "ro");
+        crate::selftest::ensure_eq(targets.len(), 1, "sin version")?;
+        crate::selftest::ensure_eq(targets[0].title.as_str(), "Linux", "titulo sin version")?;
+        crate::selftest::ensure_eq(
+            targets[0].boot.clone(),
+            Boot::Linux {
+                kernel: String::from("/EFI/LINUX/vmlinuz"),
+                initrds: alloc::vec![String::from("/EFI/LINUX/INITRD.IMG")],
+                options: String::from("ro"),
+            },
+            "initrd sin version",
+        )
+    }
+
+    fn renders_menuentries() {
+        let target = Target::new("Bob's Linux", Boot::Chain(String::from("/EFI/bob/shimx64.efi")));
+        let entry = menuentry(&target);
+        crate::selftest::ensure(entry.starts_with("menuentry 'Bob'\\''s Linux' {\r\n"), "comillas")?;
+        crate::selftest::ensure(entry.contains("chainloader ($osroot)/EFI/bob/shimx64.efi"), "chainloader")?;
+        let mut targets = alloc::vec![target.clone()];
+        push_unique(&mut targets, Target::new("Otro", Boot::Chain(String::from("/EFI/BOB/SHIMX64.EFI"))));
+        crate::selftest::ensure_eq(targets.len(), 1, "duplicado")?;
+        let cfg = crate::build_forced_grub_config_payload(ZENOX_LOADER, &targets);
+        let text = core::str::from_utf8(cfg.as_slice()).map_err(|_| "utf8")?;
+        crate::selftest::ensure(is_generated(text), "marca")?;
+        crate::selftest::ensure(text.contains("menuentry 'Bob'\\''s Linux'"), "entrada en cfg")
+    }
+}
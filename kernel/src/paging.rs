@@ -1,4 +1,5 @@
 use core::arch::asm;
+use core::sync::atomic::{AtomicBool, Ordering};
 use crate::memory::{alloc_frame, PAGE_SIZE};
 
 #[derive(Clone, Copy)]
@@ -21,6 +22,10 @@ impl PageTableEntry {
     pub fn set_user(&mut self, user: bool) {
         if user { self.0 |= 1 << 2; } else { self.0 &= !(1 << 2); }
     }
+
+    pub fn set_no_execute(&mut self, no_execute: bool) {
+        if no_execute { self.0 |= 1 << 63; } else { self.0 &= !(1 << 63); }
+    }
     
     pub fn addr(&self) -> u64 {
         self.0 & 0x000FFFFFFFFFF000
@@ -117,23 +122,235 @@ fn get_or_alloc_table(parent_entry: &mut PageTableEntry) -> Option<&mut PageTabl
     Some(unsafe { &mut *(parent_entry.addr() as *mut PageTable) })
 }
 
-/// Mapea una dirección virtual a una física en el PML4 dado.
+/// Mapea una dirección virtual a una física en el PML4 dado. Con NX activo
+/// las paginas escribibles no son ejecutables (W^X).
 pub fn map_page(pml4_phys: u64, virt: u64, phys: u64, user: bool, writable: bool) -> Result<(), &'static str> {
-    let p4_idx = ((virt >> 39) & 0177) as usize;
-    let p3_idx = ((virt >> 30) & 0177) as usize;
-    let p2_idx = ((virt >> 21) & 0177) as usize;
-    let p1_idx = ((virt >> 12) & 0177) as usize;
-    
-    let pml4 = unsafe { &mut *(pml4_phys as *mut PageTable) };
-    let pdpt = get_or_alloc_table(&mut pml4.entries[p4_idx]).ok_or("OOM en PDPT")?;
-    let pd = get_or_alloc_table(&mut pdpt.entries[p3_idx]).ok_or("OOM en PD")?;
-    let pt = get_or_alloc_table(&mut pd.entries[p2_idx]).ok_or("OOM en PT")?;
-    
-    let entry = &mut pt.entries[p1_idx];
-    entry.set_addr(phys);
-    entry.set_present(true);
-    entry.set_writable(writable);
-    entry.set_user(user);
-    
-    Ok(())
+    with_tables_writable(|| {
+        let pml4 = unsafe { &mut *(pml4_phys as *mut PageTable) };
+        let pdpt = get_or_alloc_table(&mut pml4.entries[table_index(virt, 39)]).ok_or("OOM en PDPT")?;
+        let e3 = &mut pdpt.entries[table_index(virt, 30)];
+        if e3.is_present() && e3.raw() & PTE_HUGE != 0 {
+            split_huge(e3, 1 << 30).ok_or("OOM al partir 1 GiB")?;
+        }
+        let pd = get_or_alloc_table(e3).ok_or("OOM en PD")?;
+        let e2 = &mut pd.entries[table_index(virt, 21)];
+        if e2.is_present() && e2.raw() & PTE_HUGE != 0 {
+            split_huge(e2, 1 << 21).ok_or("OOM al partir 2 MiB")?;
+        }
+        let pt = get_or_alloc_table(e2).ok_or("OOM en PT")?;
+
+        let entry = &mut pt.entries[table_index(virt, 12)];
+        entry.set_addr(phys);
+        entry.set_present(true);
+        entry.set_writable(writable);
+        entry.set_user(user);
+        entry.set_no_execute(writable && nx_enabled());
+        unsafe {
+            asm!("invlpg [{}]", in(reg) virt, options(nostack, preserves_flags));
+        }
+        Ok(())
+    })
+}
+
+const PTE_PRESENT: u64 = 1;
+const PTE_WRITABLE: u64 = 1 << 1;
+const PTE_USER: u64 = 1 << 2;
+const PTE_HUGE: u64 = 1 << 7;
+/// Bit PAT de una entrada de 2 MiB / 1 GiB; en las de 4 KiB es el bit 7.
+const PTE_HUGE_PAT: u64 = 1 << 12;
+const PTE_NO_EXECUTE: u64 = 1 << 63;
+const CR0_WP: u64 = 1 << 16;
+const IA32_EFER: u32 = 0xC000_0080;
+const EFER_NXE: u64 = 1 << 11;
+
+static NX_ENABLED: AtomicBool = AtomicBool::new(false);
+
+fn table_index(virt: u64, shift: u32) -> usize {
+    ((virt >> shift) & 0x1FF) as usize
+}
+
+fn kernel_root() -> u64 {
+    let cr3 = unsafe { KERNEL_CR3 };
+    let cr3 = if cr3 != 0 { cr3 } else { get_current_cr3() };
+    cr3 & 0x000FFFFFFFFFF000
+}
+
+/// Ejecuta `f` con CR0.WP apagado: el firmware puede dejar sus tablas de
+/// paginas en solo lectura.
+fn with_tables_writable<T>(f: impl FnOnce() -> T) -> T {
+    let cr0: u64;
+    unsafe {
+        asm!("mov {}, cr0", out(reg) cr0, options(nomem, nostack, preserves_flags));
+        asm!("mov cr0, {}", in(reg) cr0 & !CR0_WP, options(nostack, preserves_flags));
+    }
+    let result = f();
+    unsafe {
+        asm!("mov cr0, {}", in(reg) cr0, options(nostack, preserves_flags));
+    }
+    result
+}
+
+/// Sustituye la pagina grande de `entry` (de `size` bytes) por una tabla de
+/// 512 entradas con la misma traduccion y los mismos permisos.
+fn split_huge(entry: &mut PageTableEntry, size: u64) -> Option<()> {
+    let frame = alloc_frame()?;
+    let table = unsafe { &mut *(frame as *mut PageTable) };
+    let raw = entry.raw();
+    let addr_mask = 0x000F_FFFF_FFFF_F000 & !(size - 1);
+    let base = raw & addr_mask;
+    let flags = raw & !addr_mask;
+    let child_size = size / 512;
+    let child_flags = if child_size == 4096 {
+        // Las entradas de 4 KiB no llevan PS y guardan PAT en el bit 7.
+        let pat = if flags & PTE_HUGE_PAT != 0 { PTE_HUGE } else { 0 };
+        (flags & !(PTE_HUGE | PTE_HUGE_PAT)) | pat
+    } else {
+        flags
+    };
+    for (i, child) in table.entries.iter_mut().enumerate() {
+        child.set_raw(child_flags | (base + i as u64 * child_size));
+    }
+    entry.set_raw(frame | PTE_PRESENT | PTE_WRITABLE | (raw & PTE_USER));
+    Some(())
+}
+
+/// Entrada que traduce `virt` en el mapa del kernel y el tamano que cubre.
+/// Una pagina grande se devuelve entera si empieza en `virt` y acaba antes
+/// de `end`; si no, se parte. Solo dentro de `with_tables_writable`.
+unsafe fn kernel_leaf(virt: u64, end: u64) -> Result<(&'static mut PageTableEntry, u64), &'static str> {
+    let mut table = kernel_root();
+    for (shift, missing) in [(39u32, "sin PDPT"), (30, "sin PD"), (21, "sin PT")] {
+        let entry = &mut (*(table as *mut PageTable)).entries[table_index(virt, shift)];
+        if !entry.is_present() {
+            return Err(missing);
+        }
+        if shift != 39 && entry.raw() & PTE_HUGE != 0 {
+            let size = 1u64 << shift;
+            if virt & (size - 1) == 0 && end.saturating_sub(virt) >= size {
+                return Ok((entry, size));
+            }
+            split_huge(entry, size).ok_or("OOM al partir pagina grande")?;
+        }
+        table = entry.addr();
+    }
+    Ok((&mut (*(table as *mut PageTable)).entries[table_index(virt, 12)], PAGE_SIZE))
+}
+
+/// Quita del mapa del kernel la pagina de 4 KiB en `virt`, partiendo las
+/// paginas grandes que la contengan. Los PML4 de procesos comparten las
+/// tablas de niveles inferiores, asi que tambien desaparece de ellos.
+pub fn unmap_kernel_page(virt: u64) -> Result<(), &'static str> {
+    with_tables_writable(|| unsafe {
+        let (entry, _) = kernel_leaf(virt, virt + PAGE_SIZE)?;
+        entry.set_present(false);
+        asm!("invlpg [{}]", in(reg) virt, options(nostack, preserves_flags));
+        Ok(())
+    })
+}
+
+/// Si la CPU tiene bit NX (CPUID 0x8000_0001, EDX bit 20).
+pub fn nx_supported() -> bool {
+    let max_ext = unsafe { core::arch::x86_64::__cpuid(0x8000_0000) }.eax;
+    max_ext >= 0x8000_0001 && unsafe { core::arch::x86_64::__cpuid(0x8000_0001) }.edx & (1 << 20) != 0
+}
+
+/// Activa EFER.NXE en esta CPU. Hasta entonces el bit 63 de una entrada es
+/// reservado y ninguna tabla puede llevarlo. Los trampolines de los AP y
+/// de S3 lo activan por su cuenta antes de cargar CR3.
+pub fn enable_nx() -> bool {
+    if !nx_supported() {
+        return false;
+    }
+    unsafe {
+        let efer = crate::hal::rdmsr(IA32_EFER);
+        crate::hal::wrmsr(IA32_EFER, efer | EFER_NXE);
+    }
+    NX_ENABLED.store(true, Ordering::Release);
+    true
+}
+
+pub fn nx_enabled() -> bool {
+    NX_ENABLED.load(Ordering::Acquire)
+}
+
+/// Activa CR0.WP: las paginas de solo lectura tambien lo son para el kernel.
+pub fn enable_write_protect() {
+    unsafe {
+        let cr0: u64;
+        asm!("mov {}, cr0", out(reg) cr0, options(nomem, nostack, preserves_flags));
+        asm!("mov cr0, {}", in(reg) cr0 | CR0_WP, options(nostack, preserves_flags));
+    }
+}
+
+/// Cambia los permisos de `[start, end)` (alineado a pagina) en el mapa del
+/// kernel. Las paginas grandes que caben enteras no se parten y las que no
+/// tienen traduccion se saltan. Devuelve cuantas entradas cambiaron. Sin NX
+/// activo `executable` no tiene efecto.
+pub fn protect_kernel_range(start: u64, end: u64, writable: bool, executable: bool) -> Result<usize, &'static str> {
+    if start & (PAGE_SIZE - 1) != 0 || end & (PAGE_SIZE - 1) != 0 {
+        return Err("rango sin alinear a pagina");
+    }
+    let no_execute = !executable && nx_enabled();
+    let changed = with_tables_writable(|| -> Result<usize, &'static str> {
+        let mut virt = start;
+        let mut changed = 0usize;
+        while virt < end {
+            let (entry, size) = unsafe { kernel_leaf(virt, end)? };
+            if entry.is_present() {
+                entry.set_writable(writable);
+                entry.set_no_execute(no_execute);
+                changed += 1;
+            }
+            virt += size;
+        }
+        Ok(changed)
+    })?;
+    // Recargar CR3 vacia la TLB de esta CPU.
+    unsafe { set_cr3(get_current_cr3()) };
+    Ok(changed)
+}
+
+/// Permisos efectivos de `virt` en el mapa del kernel: `(escritura,
+/// ejecucion)` sumando todos los niveles, o `None` sin traduccion.
+pub fn kernel_page_access(virt: u64) -> Option<(bool, bool)> {
+    let mut table = kernel_root();
+    let mut writable = true;
+    let mut executable = true;
+    for shift in [39u32, 30, 21, 12] {
+        let entry = unsafe { (*(table as *const PageTable)).entries[table_index(virt, shift)] };
+        if !entry.is_present() {
+            return None;
+        }
+        writable &= entry.raw() & PTE_WRITABLE != 0;
+        executable &= entry.raw() & PTE_NO_EXECUTE == 0 || !nx_enabled();
+        if shift == 12 || (shift != 39 && entry.raw() & PTE_HUGE != 0) {
+            break;
+        }
+        table = entry.addr();
+    }
+    Some((writable, executable))
+}
+
+/// Si el modo usuario llega a `virt` en el mapa actual (bit U en todos los
+/// niveles), o `None` sin traduccion.
+pub fn page_user_accessible(virt: u64) -> Option<bool> {
+    let mut table = get_current_cr3() & 0x000FFFFFFFFFF000;
+    let mut user = true;
+    for shift in [39u32, 30, 21, 12] {
+        let entry = unsafe { (*(table as *const PageTable)).entries[table_index(virt, shift)] };
+        if !entry.is_present() {
+            return None;
+        }
+        user &= entry.raw() & PTE_USER != 0;
+        if shift == 12 || (shift != 39 && entry.raw() & PTE_HUGE != 0) {
+            break;
+        }
+        table = entry.addr();
+    }
+    Some(user)
+}
+
+/// Si `virt` tiene traduccion en el mapa del kernel.
+pub fn kernel_page_mapped(virt: u64) -> bool {
+    kernel_page_access(virt).is_some()
 }
@@ -1,5 +1,9 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
 use crate::hal::{outl, inl};
 use crate::println;
+use crate::spinlock::SpinLock;
 
 const CONFIG_ADDRESS: u16 = 0xCF8;
 const CONFIG_DATA: u16 = 0xCFC;
@@ -13,6 +17,144 @@ pub struct PciDevice {
     pub device_id: u16,
 }
 
+/// Snapshot of one function found during `scan`, kept for inventory commands.
+#[derive(Debug, Clone, Copy)]
+pub struct PciDeviceInfo {
+    pub device: PciDevice,
+    pub class_code: u8,
+    pub sub_class: u8,
+    pub prog_if: u8,
+    pub revision: u8,
+    pub subsys_vendor_id: u16,
+    pub subsys_id: u16,
+    /// Name of the driver that claimed the function, if any.
+    pub driver: Option<&'static str>,
+}
+
+pub const PCI_ANY_ID: u16 = 0xFFFF;
+pub const PCI_ANY_CLASS: u8 = 0xFF;
+
+/// One entry of a driver match table. `PCI_ANY_ID` / `PCI_ANY_CLASS` are wildcards.
+#[derive(Debug, Clone, Copy)]
+pub struct PciMatch {
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class_code: u8,
+    pub sub_class: u8,
+}
+
+impl PciMatch {
+    pub const fn device(vendor_id: u16, device_id: u16) -> Self {
+        Self { vendor_id, device_id, class_code: PCI_ANY_CLASS, sub_class: PCI_ANY_CLASS }
+    }
+
+    pub const fn vendor(vendor_id: u16) -> Self {
+        Self::device(vendor_id, PCI_ANY_ID)
+    }
+
+    pub const fn class(class_code: u8, sub_class: u8) -> Self {
+        Self { vendor_id: PCI_ANY_ID, device_id: PCI_ANY_ID, class_code, sub_class }
+    }
+
+    pub const fn vendor_class(vendor_id: u16, class_code: u8, sub_class: u8) -> Self {
+        Self { vendor_id, device_id: PCI_ANY_ID, class_code, sub_class }
+    }
+
+    pub fn matches(&self, info: &PciDeviceInfo) -> bool {
+        (self.vendor_id == PCI_ANY_ID || self.vendor_id == info.device.vendor_id)
+            && (self.device_id == PCI_ANY_ID || self.device_id == info.device.device_id)
+            && (self.class_code == PCI_ANY_CLASS || self.class_code == info.class_code)
+            && (self.sub_class == PCI_ANY_CLASS || self.sub_class == info.sub_class)
+    }
+}
+
+/// A PCI driver: a match table plus the probe routine called for each claimed function.
+pub struct PciDriver {
+    pub name: &'static str,
+    pub ids: &'static [PciMatch],
+    pub probe: fn(PciDevice),
+}
+
+impl PciDriver {
+    pub fn claims(&self, info: &PciDeviceInfo) -> bool {
+        self.ids.iter().any(|m| m.matches(info))
+    }
+}
+
+/// Result of a bus (re)scan.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScanReport {
+    pub found: usize,
+    pub bound: usize,
+    pub added: usize,
+    pub removed: usize,
+}
+
+// Neither lock is held across a probe, a println or a call out of this
+// module: probes call back into `devices` and `register_driver`.
+static PCI_DEVICES: SpinLock<Vec<PciDeviceInfo>> = SpinLock::new(Vec::new());
+static PCI_DRIVERS: SpinLock<Vec<&'static PciDriver>> = SpinLock::new(Vec::new());
+
+/// Devices discovered by the last `scan`.
+pub fn devices() -> Vec<PciDeviceInfo> {
+    PCI_DEVICES.lock().clone()
+}
+
+/// Register a driver. Drivers are tried in registration order; the first match wins.
+/// Devices already enumerated but still unclaimed are offered to the new driver.
+pub fn register_driver(driver: &'static PciDriver) {
+    {
+        let mut drivers = PCI_DRIVERS.lock();
+        if drivers.iter().any(|d| d.name == driver.name) {
+            return;
+        }
+        drivers.push(driver);
+    }
+    for info in devices().iter() {
+        if info.driver.is_none() && driver.claims(info) && crate::thunderbolt::admit(info) && claim(info, driver.name) {
+            crate::driver_guard::call(driver.name, || (driver.probe)(info.device));
+        }
+    }
+}
+
+pub fn drivers() -> Vec<&'static str> {
+    PCI_DRIVERS.lock().iter().map(|d| d.name).collect()
+}
+
+/// Record `driver` for the function of `info`; false when the function is
+/// gone or already has a driver.
+fn claim(info: &PciDeviceInfo, driver: &'static str) -> bool {
+    let mut devices = PCI_DEVICES.lock();
+    match devices.iter_mut().find(|d| same_function(d, info)) {
+        Some(d) if d.driver.is_none() => {
+            d.driver = Some(driver);
+            true
+        }
+        _ => false,
+    }
+}
+
+fn register_builtin_drivers() {
+    // Order matters: it mirrors the priority the old ad hoc probe chain had.
+    let builtin: [&'static PciDriver; 8] = [
+        &crate::virtio::PCI_DRIVER,
+        &crate::nvme::PCI_DRIVER,
+        &crate::thunderbolt::PCI_DRIVER,
+        &crate::xhci::PCI_DRIVER,
+        &crate::audio::PCI_DRIVER,
+        &crate::intel_xe::PCI_DRIVER,
+        &crate::intel_wifi::PCI_DRIVER,
+        &crate::intel_net::PCI_DRIVER,
+    ];
+    for driver in builtin {
+        if crate::safemode::allows_driver(driver.name) {
+            register_driver(driver);
+        } else {
+            println(alloc::format!("PCI: safe mode, driver {} skipped.", driver.name).as_str());
+        }
+    }
+}
+
 pub unsafe fn write_config(bus: u8, slot: u8, func: u8, offset: u8, value: u32) {
     let address = 0x80000000
         | ((bus as u32) << 16)
@@ -69,9 +211,41 @@ pub unsafe fn enable_bus_master(bus: u8, slot: u8, func: u8) {
     }
 }
 
-pub fn scan() {
+pub unsafe fn enable_memory_space(bus: u8, slot: u8, func: u8) {
+    let cmd = read_config(bus, slot, func, 0x04);
+    if (cmd & 0x02) == 0 {
+        write_config(bus, slot, func, 0x04, cmd | 0x02);
+    }
+}
+
+/// Walk the standard capability list. Returns `(cap_id, config_offset)` pairs.
+pub fn capabilities(bus: u8, slot: u8, func: u8) -> Vec<(u8, u8)> {
+    let mut out = Vec::new();
+    let status = unsafe { read_config(bus, slot, func, 0x04) } >> 16;
+    if (status & 0x10) == 0 {
+        return out;
+    }
+    let mut ptr = (unsafe { read_config(bus, slot, func, 0x34) } & 0xFC) as u8;
+    // Bound the walk so a malformed list cannot loop forever.
+    while ptr >= 0x40 && out.len() < 48 {
+        let header = unsafe { read_config(bus, slot, func, ptr) };
+        out.push(((header & 0xFF) as u8, ptr));
+        ptr = ((header >> 8) & 0xFC) as u8;
+    }
+    out
+}
+
+/// Enumerate the bus and bind drivers. Safe to call again for a hot rescan:
+/// functions that were already bound keep their driver and are not re-probed.
+pub fn scan() -> ScanReport {
     println("Scanning PCI bus...");
-    
+    let no_drivers = PCI_DRIVERS.lock().is_empty();
+    if no_drivers {
+        register_builtin_drivers();
+    }
+
+    let previous = devices();
+    let mut found = Vec::new();
     for bus in 0..=255 {
         for slot in 0..32 {
             let vendor_id = unsafe { read_config(bus, slot, 0, 0x00) as u16 };
@@ -81,8 +255,8 @@ pub fn scan() {
 
             let device_id = unsafe { (read_config(bus, slot, 0, 0x00) >> 16) as u16 };
             let header_type = unsafe { (read_config(bus, slot, 0, 0x0C) >> 16) as u8 };
-            
-            check_function(bus, slot, 0, vendor_id, device_id);
+
+            found.push(read_function(bus, slot, 0, vendor_id, device_id));
 
             // Multi-function device?
             if (header_type & 0x80) != 0 {
@@ -90,57 +264,167 @@ pub fn scan() {
                     let vid = unsafe { read_config(bus, slot, func, 0x00) as u16 };
                     if vid != 0xFFFF {
                         let did = unsafe { (read_config(bus, slot, func, 0x00) >> 16) as u16 };
-                        check_function(bus, slot, func, vid, did);
+                        found.push(read_function(bus, slot, func, vid, did));
                     }
                 }
             }
         }
     }
+
+    let mut report = ScanReport { found: found.len(), ..ScanReport::default() };
+    for info in found.iter_mut() {
+        match previous.iter().find(|p| same_function(p, info)) {
+            Some(prev) => info.driver = prev.driver,
+            None => report.added += 1,
+        }
+    }
+    report.removed = previous
+        .iter()
+        .filter(|p| !found.iter().any(|f| same_function(p, f)))
+        .count();
+
+    *PCI_DEVICES.lock() = found.clone();
+    crate::thunderbolt::update_topology(&found);
+    for info in found.iter() {
+        if info.driver.is_none() && crate::thunderbolt::admit(info) {
+            bind_function(info);
+        }
+    }
+    let bound = devices();
+    report.bound = bound.iter().filter(|d| d.driver.is_some()).count();
+    crate::device::sync_pci(&bound);
+    crate::thunderbolt::apply_policy();
+    report
 }
 
-fn check_function(bus: u8, slot: u8, func: u8, vendor_id: u16, device_id: u16) {
+/// Bind a function `scan` held back until its Thunderbolt attachment was
+/// authorized (see `thunderbolt::admit`).
+pub fn bind_held(device: &PciDevice) -> Option<&'static str> {
+    let at = |d: &PciDeviceInfo| {
+        d.device.bus == device.bus && d.device.slot == device.slot && d.device.func == device.func
+    };
+    let info = devices().into_iter().find(|d| at(d))?;
+    if info.driver.is_some() {
+        return info.driver;
+    }
+    bind_function(&info);
+    let devices = devices();
+    crate::device::sync_pci(&devices);
+    devices.iter().find(|d| at(d)).and_then(|d| d.driver)
+}
+
+fn same_function(a: &PciDeviceInfo, b: &PciDeviceInfo) -> bool {
+    a.device.bus == b.device.bus
+        && a.device.slot == b.device.slot
+        && a.device.func == b.device.func
+        && a.device.vendor_id == b.device.vendor_id
+        && a.device.device_id == b.device.device_id
+}
+
+fn read_function(bus: u8, slot: u8, func: u8, vendor_id: u16, device_id: u16) -> PciDeviceInfo {
     let class_rev = unsafe { read_config(bus, slot, func, 0x08) };
-    let class_code = ((class_rev >> 24) & 0xFF) as u8;
-    let sub_class = ((class_rev >> 16) & 0xFF) as u8;
+    let subsys = unsafe { read_config(bus, slot, func, 0x2C) };
+    PciDeviceInfo {
+        device: PciDevice { bus, slot, func, vendor_id, device_id },
+        class_code: ((class_rev >> 24) & 0xFF) as u8,
+        sub_class: ((class_rev >> 16) & 0xFF) as u8,
+        prog_if: ((class_rev >> 8) & 0xFF) as u8,
+        revision: (class_rev & 0xFF) as u8,
+        subsys_vendor_id: (subsys & 0xFFFF) as u16,
+        subsys_id: (subsys >> 16) as u16,
+        driver: None,
+    }
+}
+
+fn bind_function(info: &PciDeviceInfo) {
+    let dev = info.device;
 
     // Log ALL multimedia devices for diagnostics
-    if class_code == 0x04 {
+    if info.class_code == 0x04 {
         crate::println(alloc::format!(
             "PCI Audio: bus={} slot={} vendor={:#06x} device={:#06x} sub={:#04x}",
-            bus, slot, vendor_id, device_id, sub_class
+            dev.bus, dev.slot, dev.vendor_id, dev.device_id, info.sub_class
         ).as_str());
     }
 
-    if vendor_id == 0x1AF4 {
-        crate::println("Found VirtIO Device (1AF4)");
-         // device_id 0x1000..0x103F for legacy, 0x1040+ for modern
-        crate::virtio::probe(PciDevice {
-            bus,
-            slot,
-            func,
-            vendor_id,
-            device_id,
-        });
-    } else if class_code == 0x01 && sub_class == 0x08 {
-        crate::println("Found NVMe Controller");
-        crate::nvme::init(PciDevice { bus, slot, func, vendor_id, device_id });
-    } else if class_code == 0x0C && sub_class == 0x03 {
-        crate::println("Found xHCI (USB 3.0) Controller");
-        crate::xhci::init(PciDevice { bus, slot, func, vendor_id, device_id });
-    } else if class_code == 0x04 && (sub_class == 0x03 || sub_class == 0x01) {
-        crate::println("Found Intel HDA Audio Controller");
-        crate::audio::init(PciDevice { bus, slot, func, vendor_id, device_id });
-    } else if vendor_id == 0x8086 && class_code == 0x03 {
-        // Intel Display Controller (VGA/3D)
-        crate::println("Found Intel Graphics Controller");
-        crate::intel_xe::init(PciDevice { bus, slot, func, vendor_id, device_id });
-    } else if vendor_id == 0x8086 && class_code == 0x02 && sub_class == 0x80 {
-        // Intel Wireless Network Controller
-        crate::println("Found Intel Wireless Controller");
-        crate::intel_wifi::init(PciDevice { bus, slot, func, vendor_id, device_id });
-    } else if vendor_id == 0x8086 && class_code == 0x02 && sub_class == 0x00 {
-        // Intel Ethernet Controller
-        crate::println("Found Intel Ethernet Controller");
-        crate::intel_net::init(PciDevice { bus, slot, func, vendor_id, device_id });
+    let driver = PCI_DRIVERS.lock().iter().copied().find(|d| d.claims(info));
+    let Some(driver) = driver else {
+        return;
+    };
+    if !claim(info, driver.name) {
+        return;
+    }
+    crate::println(alloc::format!(
+        "PCI: {:02x}:{:02x}.{} [{:04x}:{:04x}] -> {}",
+        dev.bus, dev.slot, dev.func, dev.vendor_id, dev.device_id, driver.name
+    ).as_str());
+    // A probe that faults leaves the function with its (now disabled) driver.
+    crate::driver_guard::call(driver.name, || (driver.probe)(dev));
+}
+
+/// Shared implementation of the `lspci [-v] | lspci rescan | lspci drivers` command.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let arg = args.trim();
+    let mut out = Vec::new();
+
+    if arg.eq_ignore_ascii_case("rescan") {
+        let report = scan();
+        out.push(alloc::format!(
+            "PCI rescan: {} funciones, {} nuevas, {} retiradas, {} con driver.",
+            report.found, report.added, report.removed, report.bound
+        ));
+        return out;
+    }
+
+    if arg.eq_ignore_ascii_case("drivers") {
+        for name in drivers().iter() {
+            let count = devices().iter().filter(|d| d.driver == Some(*name)).count();
+            out.push(alloc::format!("  {:<12} {} dispositivo(s)", name, count));
+            if let Some(failure) = crate::driver_guard::failure(name) {
+                let at_ms = failure.at_ms;
+                out.push(alloc::format!("    desactivado a los {} ms: {}", at_ms, failure.reason));
+            }
+        }
+        return out;
+    }
+
+    let verbose = arg == "-v";
+    if !arg.is_empty() && !verbose {
+        out.push(String::from("Uso: lspci [-v] | lspci rescan | lspci drivers"));
+        return out;
+    }
+
+    let list = devices();
+    if list.is_empty() {
+        out.push(String::from("PCI: no hay dispositivos enumerados."));
+    }
+    for info in list.iter() {
+        let dev = info.device;
+        let name = crate::pci_ids::device_name(dev.vendor_id, dev.device_id)
+            .unwrap_or(crate::pci_ids::class_name(info.class_code, info.sub_class));
+        out.push(alloc::format!(
+            "{:02x}:{:02x}.{} [{:04x}:{:04x}] {} {} [{}]",
+            dev.bus,
+            dev.slot,
+            dev.func,
+            dev.vendor_id,
+            dev.device_id,
+            crate::pci_ids::vendor_name(dev.vendor_id).unwrap_or("?"),
+            name,
+            info.driver.unwrap_or("sin driver")
+        ));
+        if verbose {
+            out.push(alloc::format!(
+                "    clase {:02x}{:02x} prog-if {:02x} rev {:02x} subsys {:04x}:{:04x} ({})",
+                info.class_code,
+                info.sub_class,
+                info.prog_if,
+                info.revision,
+                info.subsys_vendor_id,
+                info.subsys_id,
+                crate::pci_ids::class_name(info.class_code, info.sub_class)
+            ));
+        }
     }
+    out
 }
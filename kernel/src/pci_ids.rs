@@ -0,0 +1,137 @@
+//! Small embedded subset of the PCI ID database.
+//!
+//! Covers the vendors and devices seen on the hardware and hypervisors the
+//! kernel is tested on; anything else is shown as raw `vvvv:dddd`.
+
+const VENDORS: &[(u16, &str)] = &[
+    (0x1002, "AMD/ATI"),
+    (0x1022, "AMD"),
+    (0x102B, "Matrox"),
+    (0x106B, "Apple"),
+    (0x10DE, "NVIDIA"),
+    (0x10EC, "Realtek"),
+    (0x14E4, "Broadcom"),
+    (0x15AD, "VMware"),
+    (0x168C, "Qualcomm Atheros"),
+    (0x1234, "QEMU/Bochs"),
+    (0x1414, "Microsoft Hyper-V"),
+    (0x144D, "Samsung"),
+    (0x15B7, "Sandisk/WD"),
+    (0x1AF4, "Red Hat VirtIO"),
+    (0x1B36, "Red Hat QEMU"),
+    (0x1B21, "ASMedia"),
+    (0x1B4B, "Marvell"),
+    (0x1C5C, "SK hynix"),
+    (0x1CC1, "ADATA"),
+    (0x1D6A, "Aquantia"),
+    (0x1E0F, "KIOXIA"),
+    (0x80EE, "VirtualBox"),
+    (0x8086, "Intel"),
+];
+
+const DEVICES: &[(u16, u16, &str)] = &[
+    (0x1234, 0x1111, "Bochs VGA"),
+    (0x1AF4, 0x1000, "VirtIO network (legacy)"),
+    (0x1AF4, 0x1001, "VirtIO block (legacy)"),
+    (0x1AF4, 0x1002, "VirtIO balloon (legacy)"),
+    (0x1AF4, 0x1003, "VirtIO console (legacy)"),
+    (0x1AF4, 0x1005, "VirtIO RNG (legacy)"),
+    (0x1AF4, 0x1009, "VirtIO 9P (legacy)"),
+    (0x1AF4, 0x1041, "VirtIO network"),
+    (0x1AF4, 0x1042, "VirtIO block"),
+    (0x1AF4, 0x1043, "VirtIO console"),
+    (0x1AF4, 0x1044, "VirtIO RNG"),
+    (0x1AF4, 0x1045, "VirtIO balloon"),
+    (0x1AF4, 0x1049, "VirtIO 9P"),
+    (0x1AF4, 0x1050, "VirtIO GPU"),
+    (0x1AF4, 0x1052, "VirtIO input"),
+    (0x1AF4, 0x105A, "VirtIO filesystem"),
+    (0x1B36, 0x0001, "QEMU PCI-PCI bridge"),
+    (0x1B36, 0x0008, "QEMU PCIe host bridge"),
+    (0x1B36, 0x000C, "QEMU PCIe root port"),
+    (0x1B36, 0x000D, "QEMU XHCI controller"),
+    (0x1B36, 0x0010, "QEMU NVM Express"),
+    (0x8086, 0x100E, "82540EM Gigabit Ethernet"),
+    (0x8086, 0x10D3, "82574L Gigabit Ethernet"),
+    (0x8086, 0x1237, "440FX host bridge"),
+    (0x8086, 0x15BC, "Ethernet I219-V"),
+    (0x8086, 0x15F3, "Ethernet I225-V"),
+    (0x8086, 0x125C, "Ethernet I226-V"),
+    (0x8086, 0x2415, "82801AA AC'97 audio"),
+    (0x8086, 0x24CD, "82801DB USB2 EHCI"),
+    (0x8086, 0x2668, "82801FB HD Audio"),
+    (0x8086, 0x2918, "ICH9 LPC bridge"),
+    (0x8086, 0x2922, "ICH9 AHCI controller"),
+    (0x8086, 0x2930, "ICH9 SMBus"),
+    (0x8086, 0x293E, "ICH9 HD Audio"),
+    (0x8086, 0x29C0, "Q35 DRAM controller"),
+    (0x8086, 0x7000, "PIIX3 ISA bridge"),
+    (0x8086, 0x7010, "PIIX3 IDE"),
+    (0x8086, 0x7113, "PIIX4 ACPI"),
+    (0x8086, 0x2723, "Wi-Fi 6 AX200"),
+    (0x8086, 0x2725, "Wi-Fi 6E AX210"),
+    (0x8086, 0x51F0, "Wi-Fi 6 AX211 (Alder Lake)"),
+    (0x8086, 0x7A70, "Wi-Fi 6E AX211 (Raptor Lake)"),
+    (0x8086, 0x46A6, "Iris Xe Graphics (Alder Lake-P)"),
+    (0x8086, 0x9A49, "Iris Xe Graphics (Tiger Lake)"),
+    (0x8086, 0xA7A0, "Iris Xe Graphics (Raptor Lake-P)"),
+    (0x8086, 0x51C8, "Alder Lake HD Audio"),
+    (0x8086, 0x51ED, "Alder Lake USB 3.2 xHCI"),
+    (0x8086, 0xA0ED, "Tiger Lake USB 3.2 xHCI"),
+    (0x10EC, 0x8139, "RTL8139 Fast Ethernet"),
+    (0x10EC, 0x8168, "RTL8111/8168 Gigabit Ethernet"),
+    (0x10EC, 0x8125, "RTL8125 2.5GbE"),
+    (0x15AD, 0x0405, "SVGA II adapter"),
+    (0x15AD, 0x07B0, "VMXNET3 Ethernet"),
+    (0x80EE, 0xBEEF, "VirtualBox graphics"),
+    (0x80EE, 0xCAFE, "VirtualBox guest service"),
+];
+
+pub fn vendor_name(vendor_id: u16) -> Option<&'static str> {
+    VENDORS
+        .iter()
+        .find(|(id, _)| *id == vendor_id)
+        .map(|(_, name)| *name)
+}
+
+pub fn device_name(vendor_id: u16, device_id: u16) -> Option<&'static str> {
+    DEVICES
+        .iter()
+        .find(|(v, d, _)| *v == vendor_id && *d == device_id)
+        .map(|(_, _, name)| *name)
+}
+
+/// Human readable name for a class/subclass pair (PCI Code and ID Assignment spec).
+pub fn class_name(class_code: u8, sub_class: u8) -> &'static str {
+    match (class_code, sub_class) {
+        (0x00, _) => "Unclassified device",
+        (0x01, 0x00) => "SCSI controller",
+        (0x01, 0x01) => "IDE controller",
+        (0x01, 0x06) => "SATA controller",
+        (0x01, 0x08) => "NVMe controller",
+        (0x01, _) => "Mass storage controller",
+        (0x02, 0x00) => "Ethernet controller",
+        (0x02, 0x80) => "Network controller",
+        (0x02, _) => "Network controller",
+        (0x03, 0x00) => "VGA compatible controller",
+        (0x03, _) => "Display controller",
+        (0x04, 0x01) => "Audio device",
+        (0x04, 0x03) => "HD Audio controller",
+        (0x04, _) => "Multimedia controller",
+        (0x05, _) => "Memory controller",
+        (0x06, 0x00) => "Host bridge",
+        (0x06, 0x01) => "ISA bridge",
+        (0x06, 0x04) => "PCI bridge",
+        (0x06, _) => "Bridge",
+        (0x07, _) => "Communication controller",
+        (0x08, _) => "System peripheral",
+        (0x09, _) => "Input device controller",
+        (0x0C, 0x03) => "USB controller",
+        (0x0C, 0x05) => "SMBus",
+        (0x0C, _) => "Serial bus controller",
+        (0x0D, _) => "Wireless controller",
+        (0x10, _) => "Encryption controller",
+        (0x11, _) => "Signal processing controller",
+        _ => "Unknown class",
+    }
+}
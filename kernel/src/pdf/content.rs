@@ -0,0 +1,1354 @@
+//! Content stream interpreter (ISO 32000-1 sections 8 and 9): paths,
+//! colours, images, form XObjects and text, painted onto a `Canvas` while
+//! the page's text is collected in reading order.
+//!
+//! Embedded TrueType fonts (`FontFile2`) are drawn from their outlines.
+//! Every other font falls back to the 5x7 system glyphs laid out at the
+//! font's own advance widths, so lines keep their length even when the
+//! letter shapes are approximate. Clipping paths are reduced to their
+//! bounding box; shadings, blend modes and soft masks are ignored.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::object::{Dict, File, Lexer, Object, Stream, Token};
+use super::raster::{Canvas, Clip, Matrix, Path, Point, TRANSPARENT};
+use crate::ttf;
+
+const MAX_OPERANDS: usize = 64;
+/// `q` nesting kept; deeper saves are dropped like the matching `Q`s.
+const MAX_SAVE_DEPTH: usize = 64;
+const MAX_FORM_DEPTH: usize = 8;
+/// Operators run per page, so a looping or huge stream still finishes.
+const MAX_OPERATIONS: usize = 500_000;
+const MAX_IMAGE_PIXELS: usize = 16 * 1024 * 1024;
+const MAX_FONTS: usize = 256;
+const MAX_TO_UNICODE_ENTRIES: usize = 65_536;
+/// Drawn where an image uses a codec we do not decode (JPEG, JBIG2...).
+const IMAGE_PLACEHOLDER: u32 = 0xD0D0D0;
+/// Stand-in for pattern colours.
+const PATTERN_COLOR: u32 = 0xD8D8D8;
+
+/// Windows-1252 0x80..0x9F; the rest of WinAnsiEncoding is Latin-1.
+const WIN_ANSI_HIGH: [u16; 32] = [
+    0x20AC, 0x0081, 0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021, 0x02C6, 0x2030, 0x0160, 0x2039, 0x0152, 0x008D,
+    0x017D, 0x008F, 0x0090, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014, 0x02DC, 0x2122, 0x0161, 0x203A,
+    0x0153, 0x009D, 0x017E, 0x0178,
+];
+
+/// Glyph names from `/Differences` that are not a single letter or digit.
+const GLYPH_NAMES: [(&str, char); 76] = [
+    ("space", ' '),
+    ("exclam", '!'),
+    ("quotedbl", '"'),
+    ("numbersign", '#'),
+    ("dollar", '$'),
+    ("percent", '%'),
+    ("ampersand", '&'),
+    ("quotesingle", '\''),
+    ("quoteright", '\u{2019}'),
+    ("quoteleft", '\u{2018}'),
+    ("parenleft", '('),
+    ("parenright", ')'),
+    ("asterisk", '*'),
+    ("plus", '+'),
+    ("comma", ','),
+    ("hyphen", '-'),
+    ("period", '.'),
+    ("slash", '/'),
+    ("zero", '0'),
+    ("one", '1'),
+    ("two", '2'),
+    ("three", '3'),
+    ("four", '4'),
+    ("five", '5'),
+    ("six", '6'),
+    ("seven", '7'),
+    ("eight", '8'),
+    ("nine", '9'),
+    ("colon", ':'),
+    ("semicolon", ';'),
+    ("less", '<'),
+    ("equal", '='),
+    ("greater", '>'),
+    ("question", '?'),
+    ("at", '@'),
+    ("bracketleft", '['),
+    ("backslash", '\\'),
+    ("bracketright", ']'),
+    ("asciicircum", '^'),
+    ("underscore", '_'),
+    ("grave", '`'),
+    ("braceleft", '{'),
+    ("bar", '|'),
+    ("braceright", '}'),
+    ("asciitilde", '~'),
+    ("bullet", '\u{2022}'),
+    ("endash", '\u{2013}'),
+    ("emdash", '\u{2014}'),
+    ("quotedblleft", '\u{201C}'),
+    ("quotedblright", '\u{201D}'),
+    ("quotesinglbase", '\u{201A}'),
+    ("quotedblbase", '\u{201E}'),
+    ("ellipsis", '\u{2026}'),
+    ("exclamdown", '\u{A1}'),
+    ("questiondown", '\u{BF}'),
+    ("copyright", '\u{A9}'),
+    ("registered", '\u{AE}'),
+    ("trademark", '\u{2122}'),
+    ("degree", '\u{B0}'),
+    ("ordfeminine", '\u{AA}'),
+    ("ordmasculine", '\u{BA}'),
+    ("germandbls", '\u{DF}'),
+    ("Euro", '\u{20AC}'),
+    ("section", '\u{A7}'),
+    ("paragraph", '\u{B6}'),
+    ("periodcentered", '\u{B7}'),
+    ("guillemotleft", '\u{AB}'),
+    ("guillemotright", '\u{BB}'),
+    ("minus", '\u{2212}'),
+    ("multiply", '\u{D7}'),
+    ("divide", '\u{F7}'),
+    ("nbspace", '\u{A0}'),
+    ("fi", '\u{FB01}'),
+    ("fl", '\u{FB02}'),
+    ("ff", '\u{FB00}'),
+    ("dotlessi", '\u{131}'),
+];
+
+/// Accent suffixes of Latin glyph names (`eacute`, `Ntilde`...): the base
+/// letters each one combines with and the results, position for position.
+const ACCENTS: [(&str, &str, &str); 7] = [
+    ("acute", "aeiouy", "áéíóúý"),
+    ("grave", "aeiou", "àèìòù"),
+    ("circumflex", "aeiou", "âêîôû"),
+    ("dieresis", "aeiouy", "äëïöüÿ"),
+    ("tilde", "ano", "ãñõ"),
+    ("cedilla", "c", "ç"),
+    ("ring", "a", "å"),
+];
+
+fn win_ansi(code: u8) -> char {
+    match code {
+        0x80..=0x9F => char::from_u32(WIN_ANSI_HIGH[(code - 0x80) as usize] as u32).unwrap_or('?'),
+        _ => code as char,
+    }
+}
+
+fn glyph_name_char(name: &str) -> Option<char> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(c);
+    }
+    if let Some((_, c)) = GLYPH_NAMES.iter().find(|(n, _)| *n == name) {
+        return Some(*c);
+    }
+    for prefix in ["uni", "u"] {
+        if let Some(hex) = name.strip_prefix(prefix) {
+            if (4..=6).contains(&hex.len()) {
+                if let Some(c) = u32::from_str_radix(&hex[..hex.len().min(6)], 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                {
+                    return Some(c);
+                }
+            }
+        }
+    }
+    for (suffix, bases, accented) in ACCENTS.iter() {
+        let Some(base) = name.strip_suffix(suffix) else {
+            continue;
+        };
+        let mut base_chars = base.chars();
+        let (Some(b), None) = (base_chars.next(), base_chars.next()) else {
+            continue;
+        };
+        let index = bases.chars().position(|c| c == b.to_ascii_lowercase())?;
+        let c = accented.chars().nth(index)?;
+        return if b.is_ascii_uppercase() {
+            c.to_uppercase().next()
+        } else {
+            Some(c)
+        };
+    }
+    None
+}
+
+/// Nearest ASCII character for the 5x7 font; anything without one draws
+/// as the font's box.
+pub fn ascii_fold(c: char) -> char {
+    match c {
+        'á' | 'à' | 'ä' | 'â' | 'ã' | 'å' => 'a',
+        'é' | 'è' | 'ë' | 'ê' => 'e',
+        'í' | 'ì' | 'ï' | 'î' => 'i',
+        'ó' | 'ò' | 'ö' | 'ô' | 'õ' => 'o',
+        'ú' | 'ù' | 'ü' | 'û' => 'u',
+        'Á' | 'À' | 'Ä' | 'Â' | 'Ã' | 'Å' => 'A',
+        'É' | 'È' | 'Ë' | 'Ê' => 'E',
+        'Í' | 'Ì' | 'Ï' | 'Î' => 'I',
+        'Ó' | 'Ò' | 'Ö' | 'Ô' | 'Õ' => 'O',
+        'Ú' | 'Ù' | 'Ü' | 'Û' => 'U',
+        'ñ' => 'n',
+        'Ñ' => 'N',
+        'ç' => 'c',
+        'Ç' => 'C',
+        'ý' | 'ÿ' => 'y',
+        '\u{131}' => 'i',
+        '\u{2018}' | '\u{2019}' | '\u{201A}' => '\'',
+        '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{AB}' | '\u{BB}' => '"',
+        '\u{2013}' | '\u{2014}' | '\u{2212}' | '\u{AD}' => '-',
+        '\u{2022}' | '\u{B7}' => '*',
+        '\u{A0}' | '\t' => ' ',
+        '\u{A1}' => '!',
+        '\u{BF}' => '?',
+        c => c,
+    }
+}
+
+fn pack_rgb(r: f32, g: f32, b: f32) -> u32 {
+    let channel = |v: f32| (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u32;
+    channel(r) << 16 | channel(g) << 8 | channel(b)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Space {
+    Gray,
+    Rgb,
+    Cmyk,
+    Lab,
+    /// Base space and its packed lookup table.
+    Indexed(alloc::boxed::Box<Space>, Vec<u8>),
+    /// Separation and DeviceN, shown as gray ink coverage.
+    Tint(usize),
+    Pattern,
+}
+
+impl Space {
+    fn components(&self) -> usize {
+        match self {
+            Space::Gray | Space::Indexed(..) | Space::Pattern => 1,
+            Space::Rgb | Space::Lab => 3,
+            Space::Cmyk => 4,
+            Space::Tint(n) => (*n).clamp(1, 32),
+        }
+    }
+
+    fn initial(&self) -> u32 {
+        match self {
+            Space::Indexed(..) => self.to_rgb(&[0.0]),
+            Space::Pattern => PATTERN_COLOR,
+            _ => 0,
+        }
+    }
+
+    fn to_rgb(&self, c: &[f32]) -> u32 {
+        let at = |i: usize| c.get(i).copied().unwrap_or(0.0);
+        match self {
+            Space::Gray => pack_rgb(at(0), at(0), at(0)),
+            Space::Rgb => pack_rgb(at(0), at(1), at(2)),
+            Space::Cmyk => {
+                let k = 1.0 - at(3);
+                pack_rgb((1.0 - at(0)) * k, (1.0 - at(1)) * k, (1.0 - at(2)) * k)
+            }
+            Space::Lab => pack_rgb(at(0) / 100.0, at(0) / 100.0, at(0) / 100.0),
+            Space::Indexed(base, lookup) => {
+                let n = base.components();
+                let start = (at(0).max(0.0) as usize) * n;
+                let mut comps = [0f32; 4];
+                for (i, v) in comps.iter_mut().enumerate().take(n.min(4)) {
+                    *v = lookup.get(start + i).copied().unwrap_or(0) as f32 / 255.0;
+                }
+                base.to_rgb(&comps[..n.min(4)])
+            }
+            Space::Tint(n) => {
+                let n = (*n).clamp(1, 32);
+                let ink = (0..n).map(at).sum::<f32>() / n as f32;
+                pack_rgb(1.0 - ink, 1.0 - ink, 1.0 - ink)
+            }
+            Space::Pattern => PATTERN_COLOR,
+        }
+    }
+}
+
+fn utf16_text(bytes: &[u8], bump: u16) -> String {
+    let mut units: Vec<u16> = bytes
+        .chunks(2)
+        .map(|p| (p[0] as u16) << 8 | *p.get(1).unwrap_or(&0) as u16)
+        .collect();
+    if let Some(last) = units.last_mut() {
+        *last = last.wrapping_add(bump);
+    }
+    core::char::decode_utf16(units)
+        .map(|c| c.unwrap_or('\u{FFFD}'))
+        .collect()
+}
+
+fn code_of(bytes: &[u8]) -> u32 {
+    bytes.iter().take(4).fold(0u32, |acc, &b| acc << 8 | b as u32)
+}
+
+/// `bfchar` and `bfrange` sections of a ToUnicode CMap.
+fn parse_to_unicode(data: &[u8]) -> BTreeMap<u32, String> {
+    let mut map = BTreeMap::new();
+    let mut lexer = Lexer::new(data, 0);
+    let mut operands: Vec<Object> = Vec::new();
+    // 0 outside a section, 1 in bfchar, 3 in bfrange: entry sizes.
+    let mut entry = 0usize;
+    while let Some(token) = lexer.next() {
+        if map.len() >= MAX_TO_UNICODE_ENTRIES {
+            break;
+        }
+        if let Token::Keyword(word) = &token {
+            entry = match word.as_str() {
+                "beginbfchar" => 2,
+                "beginbfrange" => 3,
+                _ => 0,
+            };
+            operands.clear();
+            continue;
+        }
+        if entry == 0 {
+            continue;
+        }
+        let Some(object) = lexer.object_from(token, 0) else {
+            break;
+        };
+        operands.push(object);
+        if operands.len() < entry {
+            continue;
+        }
+        match (entry, operands.as_slice()) {
+            (2, [Object::String(src), Object::String(dst)]) => {
+                map.insert(code_of(src), utf16_text(dst, 0));
+            }
+            (3, [Object::String(lo), Object::String(hi), dst]) => {
+                let (lo, hi) = (code_of(lo), code_of(hi));
+                let count = hi.saturating_sub(lo).min(MAX_TO_UNICODE_ENTRIES as u32);
+                for i in 0..=count {
+                    let text = match dst {
+                        Object::String(start) => utf16_text(start, i as u16),
+                        Object::Array(items) => match items.get(i as usize) {
+                            Some(Object::String(s)) => utf16_text(s, 0),
+                            _ => break,
+                        },
+                        _ => break,
+                    };
+                    map.insert(lo + i, text);
+                }
+            }
+            _ => {}
+        }
+        operands.clear();
+    }
+    map
+}
+
+struct Outlines {
+    font: ttf::Font,
+    /// `/CIDToGIDMap` stream for CID fonts; `None` is Identity.
+    cid_to_gid: Option<Vec<u8>>,
+}
+
+struct PdfFont {
+    /// Type0 fonts with two-byte codes (Identity-H and friends).
+    two_byte: bool,
+    first_char: u32,
+    /// Advance widths in text space units (glyph units / 1000).
+    widths: Vec<f32>,
+    cid_widths: BTreeMap<u32, f32>,
+    default_width: f32,
+    encoding: Vec<char>,
+    to_unicode: BTreeMap<u32, String>,
+    outlines: Option<Outlines>,
+}
+
+impl PdfFont {
+    fn codes(&self, bytes: &[u8]) -> Vec<u32> {
+        if self.two_byte {
+            bytes.chunks(2).map(code_of).collect()
+        } else {
+            bytes.iter().map(|&b| b as u32).collect()
+        }
+    }
+
+    fn width(&self, code: u32) -> f32 {
+        if self.two_byte {
+            return self.cid_widths.get(&code).copied().unwrap_or(self.default_width);
+        }
+        if let Some(w) = code
+            .checked_sub(self.first_char)
+            .and_then(|i| self.widths.get(i as usize))
+        {
+            return *w;
+        }
+        if let (true, Some(outlines)) = (self.widths.is_empty(), self.outlines.as_ref()) {
+            let gid = self.glyph_id(code);
+            let upem = outlines.font.units_per_em().max(1) as f32;
+            return outlines.font.advance_width(gid) as f32 / upem;
+        }
+        self.default_width
+    }
+
+    fn push_text(&self, code: u32, out: &mut String) {
+        if let Some(text) = self.to_unicode.get(&code) {
+            out.push_str(text);
+        } else if !self.two_byte {
+            match self.encoding.get(code as usize).copied().unwrap_or('?') {
+                '\u{FB00}' => out.push_str("ff"),
+                '\u{FB01}' => out.push_str("fi"),
+                '\u{FB02}' => out.push_str("fl"),
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn glyph_id(&self, code: u32) -> u16 {
+        let Some(outlines) = self.outlines.as_ref() else {
+            return 0;
+        };
+        if self.two_byte {
+            return match outlines.cid_to_gid.as_ref() {
+                None => code as u16,
+                Some(map) => match map.get(code as usize * 2..code as usize * 2 + 2) {
+                    Some(pair) => (pair[0] as u16) << 8 | pair[1] as u16,
+                    None => 0,
+                },
+            };
+        }
+        let font = &outlines.font;
+        let mut gid = 0;
+        if font.has_unicode_cmap() {
+            gid = font.glyph_index(self.encoding.get(code as usize).copied().unwrap_or('\0'));
+        }
+        if gid == 0 {
+            gid = font.symbol_glyph_index(code as u8);
+        }
+        gid
+    }
+
+    /// Character drawn with the 5x7 fallback for `code`.
+    fn fallback_char(&self, code: u32) -> char {
+        let mut text = String::new();
+        self.push_text(code, &mut text);
+        ascii_fold(text.chars().next().unwrap_or('?'))
+    }
+}
+
+#[derive(Clone)]
+struct GraphicsState {
+    ctm: Matrix,
+    clip: Clip,
+    line_width: f32,
+    fill_space: Space,
+    stroke_space: Space,
+    fill: u32,
+    stroke: u32,
+    char_spacing: f32,
+    word_spacing: f32,
+    h_scale: f32,
+    leading: f32,
+    font: Option<usize>,
+    font_size: f32,
+    render_mode: i64,
+    rise: f32,
+}
+
+/// Runs content streams for one page.
+pub struct Interpreter<'a> {
+    file: &'a File,
+    canvas: Option<&'a mut Canvas>,
+    state: GraphicsState,
+    saved: Vec<GraphicsState>,
+    path: Path,
+    /// `W`/`W*` seen; the clip applies when the path is painted or ended.
+    pending_clip: bool,
+    text_matrix: Matrix,
+    line_matrix: Matrix,
+    fonts: Vec<PdfFont>,
+    font_cache: BTreeMap<u32, usize>,
+    operations: usize,
+    text: String,
+    /// Where the last glyph ended on the device and its size there.
+    last_glyph: Option<(Point, f32)>,
+}
+
+/// Concatenate a page's `/Contents`, a stream or an array of them.
+pub fn content_bytes(file: &File, contents: &Object) -> Vec<u8> {
+    let mut out = Vec::new();
+    let parts: Vec<Object> = match file.resolve(contents) {
+        Object::Array(items) => items.iter().map(|o| file.resolve(o)).collect(),
+        other => alloc::vec![other],
+    };
+    for part in parts {
+        if let Object::Stream(stream) = part {
+            if let Ok(data) = file.decode_stream(&stream) {
+                out.extend_from_slice(&data);
+                out.push(b'\n');
+            }
+        }
+    }
+    out
+}
+
+fn matrix_from(object: &Object) -> Option<Matrix> {
+    let items = object.as_array()?;
+    let v: Vec<f32> = items.iter().filter_map(Object::as_f32).collect();
+    (v.len() == 6).then(|| Matrix::new(v[0], v[1], v[2], v[3], v[4], v[5]))
+}
+
+fn is_white(b: u8) -> bool {
+    matches!(b, b' ' | b'\n' | b'\r' | b'\t' | 0 | 0x0C)
+}
+
+/// Last `N` operands as numbers.
+fn numbers<const N: usize>(operands: &[Object]) -> Option<[f32; N]> {
+    let start = operands.len().checked_sub(N)?;
+    let mut out = [0f32; N];
+    for (slot, operand) in out.iter_mut().zip(&operands[start..]) {
+        *slot = operand.as_f32()?;
+    }
+    Some(out)
+}
+
+impl<'a> Interpreter<'a> {
+    /// `canvas` may be `None` to only collect text; `clip` is the page area
+    /// in device pixels.
+    pub fn new(file: &'a File, canvas: Option<&'a mut Canvas>, ctm: Matrix, clip: Clip) -> Self {
+        Interpreter {
+            file,
+            canvas,
+            state: GraphicsState {
+                ctm,
+                clip,
+                line_width: 1.0,
+                fill_space: Space::Gray,
+                stroke_space: Space::Gray,
+                fill: 0,
+                stroke: 0,
+                char_spacing: 0.0,
+                word_spacing: 0.0,
+                h_scale: 1.0,
+                leading: 0.0,
+                font: None,
+                font_size: 0.0,
+                render_mode: 0,
+                rise: 0.0,
+            },
+            saved: Vec::new(),
+            path: Path::default(),
+            pending_clip: false,
+            text_matrix: Matrix::IDENTITY,
+            line_matrix: Matrix::IDENTITY,
+            fonts: Vec::new(),
+            font_cache: BTreeMap::new(),
+            operations: 0,
+            text: String::new(),
+            last_glyph: None,
+        }
+    }
+
+    pub fn into_text(self) -> String {
+        self.text
+    }
+
+    pub fn run(&mut self, data: &[u8], resources: &Dict, depth: usize) {
+        let mut lexer = Lexer::new(data, 0);
+        let mut operands: Vec<Object> = Vec::new();
+        while let Some(token) = lexer.next() {
+            if self.operations >= MAX_OPERATIONS {
+                return;
+            }
+            match token {
+                Token::Keyword(op) => {
+                    self.operations += 1;
+                    if op == "BI" {
+                        self.inline_image(&mut lexer, resources);
+                    } else {
+                        self.operator(op.as_str(), &operands, resources, depth);
+                    }
+                    operands.clear();
+                }
+                token => {
+                    let Some(object) = lexer.object_from(token, 0) else {
+                        return;
+                    };
+                    if operands.len() < MAX_OPERANDS {
+                        operands.push(object);
+                    }
+                }
+            }
+        }
+    }
+
+    fn point(&self, x: f32, y: f32) -> Point {
+        self.state.ctm.apply(x, y)
+    }
+
+    fn operator(&mut self, op: &str, operands: &[Object], resources: &Dict, depth: usize) {
+        match op {
+            "q" if self.saved.len() < MAX_SAVE_DEPTH => self.saved.push(self.state.clone()),
+            "Q" => {
+                if let Some(state) = self.saved.pop() {
+                    self.state = state;
+                }
+            }
+            "cm" => {
+                if let Some([a, b, c, d, e, f]) = numbers::<6>(operands) {
+                    self.state.ctm = Matrix::new(a, b, c, d, e, f).then(&self.state.ctm);
+                }
+            }
+            "w" => {
+                if let Some([w]) = numbers::<1>(operands) {
+                    self.state.line_width = w.max(0.0);
+                }
+            }
+            "m" => {
+                if let Some([x, y]) = numbers::<2>(operands) {
+                    let p = self.point(x, y);
+                    self.path.move_to(p);
+                }
+            }
+            "l" => {
+                if let Some([x, y]) = numbers::<2>(operands) {
+                    let p = self.point(x, y);
+                    self.path.line_to(p);
+                }
+            }
+            "c" => {
+                if let Some([x1, y1, x2, y2, x3, y3]) = numbers::<6>(operands) {
+                    let (c1, c2, p) = (self.point(x1, y1), self.point(x2, y2), self.point(x3, y3));
+                    self.path.cubic_to(c1, c2, p);
+                }
+            }
+            "v" => {
+                if let Some([x2, y2, x3, y3]) = numbers::<4>(operands) {
+                    let c1 = self.path.current().unwrap_or_else(|| self.point(x2, y2));
+                    let (c2, p) = (self.point(x2, y2), self.point(x3, y3));
+                    self.path.cubic_to(c1, c2, p);
+                }
+            }
+            "y" => {
+                if let Some([x1, y1, x3, y3]) = numbers::<4>(operands) {
+                    let (c1, p) = (self.point(x1, y1), self.point(x3, y3));
+                    self.path.cubic_to(c1, p, p);
+                }
+            }
+            "h" => self.path.close(),
+            "re" => {
+                if let Some([x, y, w, h]) = numbers::<4>(operands) {
+                    let corners = [(x, y), (x + w, y), (x + w, y + h), (x, y + h)];
+                    self.path.move_to(self.point(x, y));
+                    for (cx, cy) in corners.iter().skip(1) {
+                        let p = self.point(*cx, *cy);
+                        self.path.line_to(p);
+                    }
+                    self.path.close();
+                }
+            }
+            "S" => self.paint(false, true, false),
+            "s" => {
+                self.path.close();
+                self.paint(false, true, false);
+            }
+            "f" | "F" => self.paint(true, false, false),
+            "f*" => self.paint(true, false, true),
+            "B" => self.paint(true, true, false),
+            "B*" => self.paint(true, true, true),
+            "b" => {
+                self.path.close();
+                self.paint(true, true, false);
+            }
+            "b*" => {
+                self.path.close();
+                self.paint(true, true, true);
+            }
+            "n" => self.end_path(),
+            "W" | "W*" => self.pending_clip = true,
+            "g" | "G" | "rg" | "RG" | "k" | "K" => {
+                let (space, n) = match op {
+                    "g" | "G" => (Space::Gray, 1),
+                    "rg" | "RG" => (Space::Rgb, 3),
+                    _ => (Space::Cmyk, 4),
+                };
+                let comps: Vec<f32> = operands.iter().rev().take(n).rev().filter_map(Object::as_f32).collect();
+                let color = space.to_rgb(&comps);
+                if op.starts_with(|c: char| c.is_ascii_uppercase()) {
+                    self.state.stroke_space = space;
+                    self.state.stroke = color;
+                } else {
+                    self.state.fill_space = space;
+                    self.state.fill = color;
+                }
+            }
+            "cs" | "CS" => {
+                let space = operands
+                    .last()
+                    .map(|o| self.space_from(o, resources, 0))
+                    .unwrap_or(Space::Gray);
+                if op == "CS" {
+                    self.state.stroke = space.initial();
+                    self.state.stroke_space = space;
+                } else {
+                    self.state.fill = space.initial();
+                    self.state.fill_space = space;
+                }
+            }
+            "sc" | "scn" | "SC" | "SCN" => {
+                let stroke = op.starts_with('S');
+                let space = if stroke {
+                    &self.state.stroke_space
+                } else {
+                    &self.state.fill_space
+                };
+                let color = if matches!(operands.last(), Some(Object::Name(_))) {
+                    PATTERN_COLOR
+                } else {
+                    let comps: Vec<f32> = operands.iter().filter_map(Object::as_f32).collect();
+                    space.to_rgb(&comps)
+                };
+                if stroke {
+                    self.state.stroke = color;
+                } else {
+                    self.state.fill = color;
+                }
+            }
+            "BT" => {
+                self.text_matrix = Matrix::IDENTITY;
+                self.line_matrix = Matrix::IDENTITY;
+            }
+            "Tc" => {
+                if let Some([v]) = numbers::<1>(operands) {
+                    self.state.char_spacing = v;
+                }
+            }
+            "Tw" => {
+                if let Some([v]) = numbers::<1>(operands) {
+                    self.state.word_spacing = v;
+                }
+            }
+            "Tz" => {
+                if let Some([v]) = numbers::<1>(operands) {
+                    self.state.h_scale = v / 100.0;
+                }
+            }
+            "TL" => {
+                if let Some([v]) = numbers::<1>(operands) {
+                    self.state.leading = v;
+                }
+            }
+            "Ts" => {
+                if let Some([v]) = numbers::<1>(operands) {
+                    self.state.rise = v;
+                }
+            }
+            "Tr" => {
+                if let Some(mode) = operands.last().and_then(Object::as_int) {
+                    self.state.render_mode = mode;
+                }
+            }
+            "Tf" => {
+                if let (Some(Object::Name(name)), Some(size)) = (
+                    operands.len().checked_sub(2).map(|i| &operands[i]),
+                    operands.last().and_then(Object::as_f32),
+                ) {
+                    self.state.font = self.font(resources, name);
+                    self.state.font_size = size;
+                }
+            }
+            "Td" => {
+                if let Some([x, y]) = numbers::<2>(operands) {
+                    self.next_line(x, y);
+                }
+            }
+            "TD" => {
+                if let Some([x, y]) = numbers::<2>(operands) {
+                    self.state.leading = -y;
+                    self.next_line(x, y);
+                }
+            }
+            "Tm" => {
+                if let Some([a, b, c, d, e, f]) = numbers::<6>(operands) {
+                    self.text_matrix = Matrix::new(a, b, c, d, e, f);
+                    self.line_matrix = self.text_matrix;
+                }
+            }
+            "T*" => self.next_line(0.0, -self.state.leading),
+            "Tj" => {
+                if let Some(Object::String(bytes)) = operands.last() {
+                    self.show_text(bytes);
+                }
+            }
+            "'" => {
+                self.next_line(0.0, -self.state.leading);
+                if let Some(Object::String(bytes)) = operands.last() {
+                    self.show_text(bytes);
+                }
+            }
+            "\"" => {
+                if let [.., aw, ac, Object::String(bytes)] = operands {
+                    self.state.word_spacing = aw.as_f32().unwrap_or(0.0);
+                    self.state.char_spacing = ac.as_f32().unwrap_or(0.0);
+                    self.next_line(0.0, -self.state.leading);
+                    self.show_text(bytes);
+                }
+            }
+            "TJ" => {
+                let Some(Object::Array(items)) = operands.last() else {
+                    return;
+                };
+                for item in items {
+                    match item {
+                        Object::String(bytes) => self.show_text(bytes),
+                        other => {
+                            if let Some(adjust) = other.as_f32() {
+                                let tx = -adjust / 1000.0 * self.state.font_size * self.state.h_scale;
+                                self.text_matrix = Matrix::translate(tx, 0.0).then(&self.text_matrix);
+                            }
+                        }
+                    }
+                }
+            }
+            "Do" => {
+                if let Some(Object::Name(name)) = operands.last() {
+                    self.xobject(resources, name, depth);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn paint(&mut self, fill: bool, stroke: bool, even_odd: bool) {
+        if let Some(canvas) = self.canvas.as_deref_mut() {
+            if fill {
+                canvas.fill(&self.path, even_odd, &self.state.clip, self.state.fill);
+            }
+            if stroke {
+                let width = self.state.line_width * self.state.ctm.scale();
+                canvas.stroke(&self.path, width, &self.state.clip, self.state.stroke);
+            }
+        }
+        self.end_path();
+    }
+
+    fn end_path(&mut self) {
+        if self.pending_clip {
+            self.state.clip = self.state.clip.intersect(&self.path.bounds());
+            self.pending_clip = false;
+        }
+        self.path = Path::default();
+    }
+
+    fn next_line(&mut self, x: f32, y: f32) {
+        self.line_matrix = Matrix::translate(x, y).then(&self.line_matrix);
+        self.text_matrix = self.line_matrix;
+    }
+
+    fn resource(&self, resources: &Dict, category: &str, name: &str) -> (Option<Object>, Object) {
+        match self.file.get_in(resources, category) {
+            Object::Dict(dict) => {
+                let raw = dict.get(name).cloned();
+                let resolved = raw.as_ref().map(|o| self.file.resolve(o)).unwrap_or(Object::Null);
+                (raw, resolved)
+            }
+            _ => (None, Object::Null),
+        }
+    }
+
+    fn space_from(&self, object: &Object, resources: &Dict, depth: usize) -> Space {
+        if depth > 4 {
+            return Space::Gray;
+        }
+        match self.file.resolve(object) {
+            Object::Name(name) => match name.as_str() {
+                "DeviceGray" | "G" | "CalGray" => Space::Gray,
+                "DeviceRGB" | "RGB" | "CalRGB" => Space::Rgb,
+                "DeviceCMYK" | "CMYK" => Space::Cmyk,
+                "Pattern" => Space::Pattern,
+                other => match self.resource(resources, "ColorSpace", other).1 {
+                    Object::Null => Space::Gray,
+                    found => self.space_from(&found, resources, depth + 1),
+                },
+            },
+            Object::Array(items) => {
+                let family = items.first().and_then(Object::as_name).unwrap_or("");
+                match family {
+                    "ICCBased" => {
+                        let n = items
+                            .get(1)
+                            .map(|s| self.file.resolve(s))
+                            .and_then(|s| s.as_dict().and_then(|d| self.file.get_in(d, "N").as_int()))
+                            .unwrap_or(3);
+                        match n {
+                            1 => Space::Gray,
+                            4 => Space::Cmyk,
+                            _ => Space::Rgb,
+                        }
+                    }
+                    "Indexed" | "I" => {
+                        let base = items
+                            .get(1)
+                            .map(|b| self.space_from(b, resources, depth + 1))
+                            .unwrap_or(Space::Rgb);
+                        let lookup = match items.get(3).map(|l| self.file.resolve(l)) {
+                            Some(Object::String(bytes)) => bytes,
+                            Some(Object::Stream(stream)) => self.file.decode_stream(&stream).unwrap_or_default(),
+                            _ => Vec::new(),
+                        };
+                        Space::Indexed(alloc::boxed::Box::new(base), lookup)
+                    }
+                    "Separation" => Space::Tint(1),
+                    "DeviceN" => Space::Tint(
+                        items
+                            .get(1)
+                            .map(|n| self.file.resolve(n))
+                            .and_then(|n| n.as_array().map(<[Object]>::len))
+                            .unwrap_or(1),
+                    ),
+                    "Lab" => Space::Lab,
+                    "Pattern" => Space::Pattern,
+                    _ => match items.first() {
+                        Some(first) => self.space_from(first, resources, depth + 1),
+                        None => Space::Gray,
+                    },
+                }
+            }
+            _ => Space::Gray,
+        }
+    }
+
+    fn font(&mut self, resources: &Dict, name: &str) -> Option<usize> {
+        let (raw, resolved) = self.resource(resources, "Font", name);
+        let key = match raw {
+            Some(Object::Ref(num, _)) => Some(num),
+            _ => None,
+        };
+        if let Some(index) = key.and_then(|k| self.font_cache.get(&k)) {
+            return Some(*index);
+        }
+        if self.fonts.len() >= MAX_FONTS {
+            return None;
+        }
+        let font = self.load_font(resolved.as_dict()?);
+        self.fonts.push(font);
+        let index = self.fonts.len() - 1;
+        if let Some(key) = key {
+            self.font_cache.insert(key, index);
+        }
+        Some(index)
+    }
+
+    fn truetype(&self, descriptor: &Object) -> Option<ttf::Font> {
+        let descriptor = descriptor.as_dict()?;
+        let Object::Stream(stream) = self.file.get_in(descriptor, "FontFile2") else {
+            return None;
+        };
+        ttf::Font::parse(self.file.decode_stream(&stream).ok()?)
+    }
+
+    fn load_font(&self, dict: &Dict) -> PdfFont {
+        let file = self.file;
+        let mut font = PdfFont {
+            two_byte: false,
+            first_char: 0,
+            widths: Vec::new(),
+            cid_widths: BTreeMap::new(),
+            default_width: 0.5,
+            encoding: (0..=255u8).map(win_ansi).collect(),
+            to_unicode: BTreeMap::new(),
+            outlines: None,
+        };
+        if let Object::Stream(stream) = file.get_in(dict, "ToUnicode") {
+            if let Ok(data) = file.decode_stream(&stream) {
+                font.to_unicode = parse_to_unicode(&data);
+            }
+        }
+        if dict.name("Subtype") == Some("Type0") {
+            font.two_byte = true;
+            let descendant = match file.get_in(dict, "DescendantFonts") {
+                Object::Array(items) => items.first().map(|d| file.resolve(d)).unwrap_or(Object::Null),
+                _ => Object::Null,
+            };
+            let Some(cid) = descendant.as_dict() else {
+                return font;
+            };
+            font.default_width = file.get_in(cid, "DW").as_f32().unwrap_or(1000.0) / 1000.0;
+            if let Object::Array(w) = file.get_in(cid, "W") {
+                let w: Vec<Object> = w.iter().map(|o| file.resolve(o)).collect();
+                let mut i = 0usize;
+                while i + 1 < w.len() && font.cid_widths.len() < MAX_TO_UNICODE_ENTRIES {
+                    let Some(first) = w[i].as_int() else {
+                        break;
+                    };
+                    if let Object::Array(list) = &w[i + 1] {
+                        for (j, v) in list.iter().enumerate() {
+                            let width = file.resolve(v).as_f32().unwrap_or(0.0);
+                            font.cid_widths.insert(first as u32 + j as u32, width / 1000.0);
+                        }
+                        i += 2;
+                    } else {
+                        let (Some(last), Some(width)) = (w[i + 1].as_int(), w.get(i + 2).and_then(Object::as_f32))
+                        else {
+                            break;
+                        };
+                        let count = (last - first).clamp(0, MAX_TO_UNICODE_ENTRIES as i64);
+                        for c in first..=first + count {
+                            font.cid_widths.insert(c as u32, width / 1000.0);
+                        }
+                        i += 3;
+                    }
+                }
+            }
+            if let Some(ttf) = self.truetype(&file.get_in(cid, "FontDescriptor")) {
+                let cid_to_gid = match file.get_in(cid, "CIDToGIDMap") {
+                    Object::Stream(stream) => file.decode_stream(&stream).ok(),
+                    _ => None,
+                };
+                font.outlines = Some(Outlines { font: ttf, cid_to_gid });
+            }
+            return font;
+        }
+
+        font.first_char = file.get_in(dict, "FirstChar").as_int().unwrap_or(0).clamp(0, 255) as u32;
+        if let Object::Array(widths) = file.get_in(dict, "Widths") {
+            font.widths = widths
+                .iter()
+                .map(|w| file.resolve(w).as_f32().unwrap_or(0.0) / 1000.0)
+                .collect();
+        }
+        let descriptor = file.get_in(dict, "FontDescriptor");
+        if let Some(missing) = descriptor
+            .as_dict()
+            .and_then(|d| file.get_in(d, "MissingWidth").as_f32())
+        {
+            font.default_width = missing / 1000.0;
+        } else if dict.name("BaseFont").is_some_and(|n| n.contains("Courier")) {
+            font.default_width = 0.6;
+        }
+        if let Object::Dict(encoding) = file.get_in(dict, "Encoding") {
+            if let Object::Array(differences) = file.get_in(&encoding, "Differences") {
+                let mut code = 0usize;
+                for item in differences.iter().map(|d| file.resolve(d)) {
+                    match item {
+                        Object::Int(start) => code = start.clamp(0, 256) as usize,
+                        Object::Name(name) => {
+                            if let (Some(slot), Some(c)) = (font.encoding.get_mut(code), glyph_name_char(&name)) {
+                                *slot = c;
+                            }
+                            code += 1;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        font.outlines = self.truetype(&descriptor).map(|ttf| Outlines {
+            font: ttf,
+            cid_to_gid: None,
+        });
+        font
+    }
+
+    fn show_text(&mut self, bytes: &[u8]) {
+        let Some(index) = self.state.font else {
+            return;
+        };
+        let state = &self.state;
+        let font = &self.fonts[index];
+        let size = state.font_size;
+        let visible = !matches!(state.render_mode, 3 | 7);
+        let color = if matches!(state.render_mode, 1 | 5) {
+            state.stroke
+        } else {
+            state.fill
+        };
+        for code in font.codes(bytes) {
+            let trm = Matrix::new(size * state.h_scale, 0.0, 0.0, size, 0.0, state.rise)
+                .then(&self.text_matrix)
+                .then(&state.ctm);
+            let width = font.width(code);
+            let origin = trm.apply(0.0, 0.0);
+            let device_size = trm.scale();
+
+            let mut glyph_text = String::new();
+            font.push_text(code, &mut glyph_text);
+            if let Some((last, last_size)) = self.last_glyph {
+                let threshold = last_size.max(device_size).max(1.0);
+                if super::raster::abs(origin.y - last.y) > threshold * 0.5 {
+                    if !self.text.ends_with('\n') {
+                        self.text.push('\n');
+                    }
+                } else if origin.x - last.x > threshold * 0.15
+                    && !self.text.ends_with(' ')
+                    && !glyph_text.starts_with(' ')
+                {
+                    self.text.push(' ');
+                }
+            }
+            self.text.push_str(&glyph_text);
+
+            if let (true, Some(canvas)) = (visible, self.canvas.as_deref_mut()) {
+                let mut path = Path::default();
+                match font.outlines.as_ref() {
+                    Some(outlines) => {
+                        let gid = font.glyph_id(code);
+                        if gid != 0 {
+                            let upem = outlines.font.units_per_em().max(1) as f32;
+                            let at = |x: i32, y: i32| trm.apply(x as f32 / upem, y as f32 / upem);
+                            for op in outlines.font.glyph_path(gid) {
+                                match op {
+                                    ttf::PathOp::Move(x, y) => path.move_to(at(x, y)),
+                                    ttf::PathOp::Line(x, y) => path.line_to(at(x, y)),
+                                    ttf::PathOp::Quad(cx, cy, x, y) => path.quad_to(at(cx, cy), at(x, y)),
+                                    ttf::PathOp::Close => path.close(),
+                                }
+                            }
+                        }
+                    }
+                    None => fallback_glyph(&mut path, &trm, font.fallback_char(code), width),
+                }
+                if !path.is_empty() {
+                    canvas.fill(&path, false, &state.clip, color);
+                }
+            }
+
+            let word = if !font.two_byte && code == 32 {
+                state.word_spacing
+            } else {
+                0.0
+            };
+            let tx = (width * size + state.char_spacing + word) * state.h_scale;
+            self.text_matrix = Matrix::translate(tx, 0.0).then(&self.text_matrix);
+            let end = Matrix::new(size * state.h_scale, 0.0, 0.0, size, 0.0, state.rise)
+                .then(&self.text_matrix)
+                .then(&state.ctm)
+                .apply(0.0, 0.0);
+            self.last_glyph = Some((end, device_size));
+        }
+    }
+
+    fn xobject(&mut self, resources: &Dict, name: &str, depth: usize) {
+        let Object::Stream(stream) = self.resource(resources, "XObject", name).1 else {
+            return;
+        };
+        match stream.dict.name("Subtype") {
+            Some("Image") => self.image(&stream, resources),
+            Some("Form") if depth < MAX_FORM_DEPTH => self.form(&stream, resources, depth),
+            _ => {}
+        }
+    }
+
+    fn form(&mut self, stream: &Stream, resources: &Dict, depth: usize) {
+        let Ok(data) = self.file.decode_stream(stream) else {
+            return;
+        };
+        let saved = self.state.clone();
+        let saved_depth = self.saved.len();
+        let matrix = matrix_from(&self.file.get_in(&stream.dict, "Matrix")).unwrap_or(Matrix::IDENTITY);
+        self.state.ctm = matrix.then(&self.state.ctm);
+        if let Object::Array(bbox) = self.file.get_in(&stream.dict, "BBox") {
+            let v: Vec<f32> = bbox.iter().filter_map(Object::as_f32).collect();
+            if v.len() == 4 {
+                let ctm = self.state.ctm;
+                let corners = [(v[0], v[1]), (v[2], v[1]), (v[0], v[3]), (v[2], v[3])];
+                let area = Clip::around(corners.iter().map(|&(x, y)| ctm.apply(x, y)));
+                self.state.clip = self.state.clip.intersect(&area);
+            }
+        }
+        let own = match self.file.get_in(&stream.dict, "Resources") {
+            Object::Dict(dict) => dict,
+            _ => resources.clone(),
+        };
+        self.path = Path::default();
+        self.run(&data, &own, depth + 1);
+        self.saved.truncate(saved_depth);
+        self.state = saved;
+    }
+
+    fn image(&mut self, stream: &Stream, resources: &Dict) {
+        if self.canvas.is_none() {
+            return;
+        }
+        let Some((width, height, pixels)) = self.image_pixels(stream, resources) else {
+            return;
+        };
+        let (ctm, clip) = (self.state.ctm, self.state.clip);
+        if let Some(canvas) = self.canvas.as_deref_mut() {
+            canvas.draw_image(&ctm, width, height, &pixels, &clip);
+        }
+    }
+
+    fn image_pixels(&self, stream: &Stream, resources: &Dict) -> Option<(u32, u32, Vec<u32>)> {
+        let file = self.file;
+        let dict = &stream.dict;
+        let int = |key: &str, short: &str| {
+            dict.get(key)
+                .or_else(|| dict.get(short))
+                .and_then(|o| file.resolve(o).as_int())
+        };
+        let width = int("Width", "W").filter(|w| *w > 0)? as usize;
+        let height = int("Height", "H").filter(|h| *h > 0)? as usize;
+        if width.saturating_mul(height) > MAX_IMAGE_PIXELS {
+            return None;
+        }
+        let data = match file.decode_stream(stream) {
+            Ok(data) => data,
+            Err(_) => return Some((1, 1, alloc::vec![IMAGE_PLACEHOLDER])),
+        };
+        let mask = dict
+            .get("ImageMask")
+            .or_else(|| dict.get("IM"))
+            .map(|o| file.resolve(o))
+            == Some(Object::Bool(true));
+        let decode = dict.get("Decode").or_else(|| dict.get("D")).map(|o| file.resolve(o));
+        let inverted = decode
+            .as_ref()
+            .and_then(|d| d.as_array())
+            .and_then(|d| d.first())
+            .and_then(Object::as_f32)
+            == Some(1.0);
+
+        let (space, bpc) = if mask {
+            (Space::Gray, 1)
+        } else {
+            let space = dict
+                .get("ColorSpace")
+                .or_else(|| dict.get("CS"))
+                .map(|cs| self.space_from(cs, resources, 0))
+                .unwrap_or(Space::Gray);
+            (space, int("BitsPerComponent", "BPC").unwrap_or(8) as usize)
+        };
+        if !matches!(bpc, 1 | 2 | 4 | 8 | 16) {
+            return None;
+        }
+        let n = space.components();
+        let row_bytes = (width * n * bpc).div_ceil(8);
+        let max = if bpc == 16 { 255 } else { (1u32 << bpc) - 1 };
+        let indexed = matches!(space, Space::Indexed(..));
+        let sample = |row: usize, index: usize| -> u32 {
+            let base = row * row_bytes;
+            match bpc {
+                8 => data.get(base + index).copied().unwrap_or(0) as u32,
+                16 => data.get(base + index * 2).copied().unwrap_or(0) as u32,
+                _ => {
+                    let bit = index * bpc;
+                    let byte = data.get(base + bit / 8).copied().unwrap_or(0) as u32;
+                    (byte >> (8 - bpc - bit % 8)) & max
+                }
+            }
+        };
+
+        let mut pixels = Vec::with_capacity(width * height);
+        let mut comps = [0f32; 32];
+        for y in 0..height {
+            for x in 0..width {
+                if mask {
+                    let painted = (sample(y, x) == 0) != inverted;
+                    pixels.push(if painted { self.state.fill } else { TRANSPARENT });
+                    continue;
+                }
+                for (i, slot) in comps.iter_mut().enumerate().take(n) {
+                    let v = sample(y, x * n + i);
+                    *slot = if indexed { v as f32 } else { v as f32 / max as f32 };
+                }
+                pixels.push(space.to_rgb(&comps[..n]));
+            }
+        }
+        Some((width as u32, height as u32, pixels))
+    }
+
+    /// `BI <dict> ID <data> EI`: expand the abbreviated keys and draw it
+    /// like an image XObject.
+    fn inline_image(&mut self, lexer: &mut Lexer, resources: &Dict) {
+        let mut dict = Dict::default();
+        loop {
+            match lexer.next() {
+                Some(Token::Keyword(k)) if k == "ID" => break,
+                Some(Token::Obj(Object::Name(key))) => {
+                    let Some(value) = lexer.object() else {
+                        return;
+                    };
+                    let key = match key.as_str() {
+                        "BPC" => "BitsPerComponent",
+                        "CS" => "ColorSpace",
+                        "D" => "Decode",
+                        "DP" => "DecodeParms",
+                        "F" => "Filter",
+                        "H" => "Height",
+                        "IM" => "ImageMask",
+                        "W" => "Width",
+                        other => other,
+                    };
+                    let value = match value {
+                        Object::Name(n) => Object::Name(String::from(match n.as_str() {
+                            "G" => "DeviceGray",
+                            "RGB" => "DeviceRGB",
+                            "CMYK" => "DeviceCMYK",
+                            "AHx" => "ASCIIHexDecode",
+                            "A85" => "ASCII85Decode",
+                            "Fl" => "FlateDecode",
+                            "DCT" => "DCTDecode",
+                            other => other,
+                        })),
+                        other => other,
+                    };
+                    dict.0.push((String::from(key), value));
+                }
+                Some(_) => {}
+                None => return,
+            }
+        }
+        let data = lexer.data;
+        let start = (lexer.pos + 1).min(data.len());
+        let mut end = start;
+        while end + 1 < data.len() {
+            let at_ei = data[end] == b'E'
+                && data[end + 1] == b'I'
+                && end > start
+                && is_white(data[end - 1])
+                && data.get(end + 2).is_none_or(|&b| is_white(b));
+            if at_ei {
+                break;
+            }
+            end += 1;
+        }
+        let end = end.min(data.len());
+        lexer.pos = (end + 2).min(data.len());
+        let stream = Stream {
+            dict,
+            raw: data[start..end.saturating_sub(1).max(start)].to_vec(),
+        };
+        self.image(&stream, resources);
+    }
+}
+
+/// Draw `c` from the 5x7 font as filled cells, seven rows over 0.7 em and
+/// five columns over most of the advance `width`.
+fn fallback_glyph(path: &mut Path, trm: &Matrix, c: char, width: f32) {
+    if c == ' ' {
+        return;
+    }
+    let width = width.clamp(0.3, 1.0);
+    let column = width * 0.8 / 5.0;
+    let left = width * 0.1;
+    for (row, bits) in crate::font::glyph_5x7(c).iter().enumerate() {
+        let mut col = 0;
+        while col < 5 {
+            if bits & (0x10 >> col) == 0 {
+                col += 1;
+                continue;
+            }
+            let run_start = col;
+            while col < 5 && bits & (0x10 >> col) != 0 {
+                col += 1;
+            }
+            let (x0, x1) = (left + run_start as f32 * column, left + col as f32 * column);
+            let (y0, y1) = (0.7 - (row + 1) as f32 * 0.1, 0.7 - row as f32 * 0.1);
+            path.move_to(trm.apply(x0, y0));
+            path.line_to(trm.apply(x1, y0));
+            path.line_to(trm.apply(x1, y1));
+            path.line_to(trm.apply(x0, y1));
+            path.close();
+        }
+    }
+}
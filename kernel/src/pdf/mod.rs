@@ -0,0 +1,422 @@
+//! PDF reader: page tree, rendering to pixels and text extraction.
+//!
+//! `Document::parse` takes the whole file; pages are rendered on demand at
+//! a zoom where 100% is one pixel per PDF point. The browser hands
+//! `application/pdf` responses here and the PDF viewer app renders one page
+//! at a time. See `object` for what the parser accepts and `content` for
+//! what gets drawn.
+
+mod content;
+mod object;
+mod raster;
+
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use object::{Dict, File, Object};
+use raster::{Canvas, Clip, Matrix};
+
+const MAX_PAGES: usize = 10_000;
+const MAX_TREE_DEPTH: usize = 32;
+/// Largest page bitmap; bigger zooms are scaled back to fit.
+const MAX_RENDER_PIXELS: f32 = 12_000_000.0;
+/// US Letter, used when a page has no usable `/MediaBox`.
+const DEFAULT_PAGE_BOX: [f32; 4] = [0.0, 0.0, 612.0, 792.0];
+pub const MIN_ZOOM: u32 = 10;
+pub const MAX_ZOOM: u32 = 400;
+
+struct Page {
+    resources: Dict,
+    contents: Object,
+    /// Visible area (CropBox, else MediaBox) as llx, lly, urx, ury.
+    bounds: [f32; 4],
+    /// Clockwise rotation in degrees: 0, 90, 180 or 270.
+    rotate: u32,
+}
+
+#[derive(Clone, Default)]
+struct Inherited {
+    resources: Option<Dict>,
+    media_box: Option<[f32; 4]>,
+    crop_box: Option<[f32; 4]>,
+    rotate: Option<i64>,
+}
+
+pub struct RenderedPage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u32>,
+    pub text: String,
+}
+
+pub struct Document {
+    file: File,
+    pages: Vec<Page>,
+}
+
+pub fn is_pdf(data: &[u8]) -> bool {
+    data[..data.len().min(1024)].windows(5).any(|w| w == b"%PDF-")
+}
+
+/// Text for the 5x7 font (shell, window titles): accents dropped and
+/// anything else outside ASCII shown as '?'.
+pub fn ascii_text(text: &str) -> String {
+    text.chars()
+        .map(content::ascii_fold)
+        .map(|c| if c.is_ascii() { c } else { '?' })
+        .collect()
+}
+
+/// PDF text strings are UTF-16BE with a BOM or PDFDocEncoding, which
+/// matches Latin-1 for everything we display.
+fn text_string(bytes: &[u8]) -> String {
+    match bytes {
+        [0xFE, 0xFF, rest @ ..] => {
+            let units = rest
+                .chunks(2)
+                .map(|p| (p[0] as u16) << 8 | *p.get(1).unwrap_or(&0) as u16);
+            core::char::decode_utf16(units).map(|c| c.unwrap_or('?')).collect()
+        }
+        _ => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+impl Document {
+    pub fn parse(data: Vec<u8>) -> Result<Document, &'static str> {
+        let file = File::open(data)?;
+        let root = file.get_in(&file.trailer, "Root");
+        let tree = root.as_dict().map(|r| file.get_in(r, "Pages")).unwrap_or(Object::Null);
+        let mut pages = Vec::new();
+        let mut visited = BTreeSet::new();
+        let tree_ref = root
+            .as_dict()
+            .and_then(|r| r.get("Pages"))
+            .cloned()
+            .unwrap_or(Object::Null);
+        if let Object::Ref(num, _) = tree_ref {
+            visited.insert(num);
+        }
+        collect_pages(&file, &tree, Inherited::default(), 0, &mut visited, &mut pages);
+        if pages.is_empty() {
+            return Err("PDF sin paginas");
+        }
+        Ok(Document { file, pages })
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// `/Title` from the document information dictionary, if any.
+    pub fn title(&self) -> Option<String> {
+        let info = self.file.get_in(&self.file.trailer, "Info");
+        let title = self.file.get_in(info.as_dict()?, "Title");
+        let text = text_string(title.as_bytes()?);
+        let text = text.trim();
+        (!text.is_empty()).then(|| String::from(text))
+    }
+
+    /// Page size in points as displayed, rotation applied.
+    pub fn page_size_pt(&self, index: usize) -> Option<(f32, f32)> {
+        let page = self.pages.get(index)?;
+        let (w, h) = (page.bounds[2] - page.bounds[0], page.bounds[3] - page.bounds[1]);
+        Some(if page.rotate % 180 == 90 { (h, w) } else { (w, h) })
+    }
+
+    /// Render page `index` (from 0) at `zoom_percent`, also returning the
+    /// page text.
+    pub fn render_page(&self, index: usize, zoom_percent: u32) -> Result<RenderedPage, &'static str> {
+        let page = self.pages.get(index).ok_or("pagina inexistente")?;
+        let (w, h) = self.page_size_pt(index).ok_or("pagina inexistente")?;
+        let mut scale = zoom_percent.clamp(MIN_ZOOM, MAX_ZOOM) as f32 / 100.0;
+        if w * h * scale * scale > MAX_RENDER_PIXELS {
+            scale = raster::sqrt(MAX_RENDER_PIXELS / (w * h));
+        }
+        let width = ((w * scale) as u32).max(1);
+        let height = ((h * scale) as u32).max(1);
+        let mut canvas = Canvas::new(width, height, 0xFFFFFF);
+        let text = self.run_page(page, scale, Some(&mut canvas), canvas_clip(width, height));
+        Ok(RenderedPage {
+            width,
+            height,
+            pixels: canvas.pixels,
+            text,
+        })
+    }
+
+    /// Text of page `index` without drawing anything.
+    pub fn page_text(&self, index: usize) -> Option<String> {
+        let page = self.pages.get(index)?;
+        let (w, h) = self.page_size_pt(index)?;
+        Some(self.run_page(page, 1.0, None, canvas_clip(w as u32 + 1, h as u32 + 1)))
+    }
+
+    fn run_page(&self, page: &Page, scale: f32, canvas: Option<&mut Canvas>, clip: Clip) -> String {
+        let data = content::content_bytes(&self.file, &page.contents);
+        let mut interpreter = content::Interpreter::new(&self.file, canvas, device_matrix(page, scale), clip);
+        interpreter.run(&data, &page.resources, 0);
+        let text = interpreter.into_text();
+        String::from(text.trim_end())
+    }
+}
+
+fn canvas_clip(width: u32, height: u32) -> Clip {
+    Clip {
+        x0: 0,
+        y0: 0,
+        x1: width as i32,
+        y1: height as i32,
+    }
+}
+
+/// Page space to device pixels: y flipped, origin at the top left of the
+/// visible box, rotated clockwise by `/Rotate`.
+fn device_matrix(page: &Page, s: f32) -> Matrix {
+    let [llx, lly, urx, ury] = page.bounds;
+    match page.rotate {
+        90 => Matrix::new(0.0, s, s, 0.0, -lly * s, -llx * s),
+        180 => Matrix::new(-s, 0.0, 0.0, s, urx * s, -lly * s),
+        270 => Matrix::new(0.0, -s, -s, 0.0, ury * s, urx * s),
+        _ => Matrix::new(s, 0.0, 0.0, -s, -llx * s, ury * s),
+    }
+}
+
+fn rect_from(file: &File, object: &Object) -> Option<[f32; 4]> {
+    let items = file.resolve(object);
+    let v: Vec<f32> = items
+        .as_array()?
+        .iter()
+        .filter_map(|o| file.resolve(o).as_f32())
+        .collect();
+    if v.len() != 4 {
+        return None;
+    }
+    let rect = [v[0].min(v[2]), v[1].min(v[3]), v[0].max(v[2]), v[1].max(v[3])];
+    (rect[2] - rect[0] >= 1.0 && rect[3] - rect[1] >= 1.0).then_some(rect)
+}
+
+fn collect_pages(
+    file: &File,
+    node: &Object,
+    mut inherited: Inherited,
+    depth: usize,
+    visited: &mut BTreeSet<u32>,
+    pages: &mut Vec<Page>,
+) {
+    let Some(dict) = node.as_dict() else {
+        return;
+    };
+    if depth > MAX_TREE_DEPTH || pages.len() >= MAX_PAGES {
+        return;
+    }
+    if let Object::Dict(resources) = file.get_in(dict, "Resources") {
+        inherited.resources = Some(resources);
+    }
+    if let Some(rect) = dict.get("MediaBox").and_then(|b| rect_from(file, b)) {
+        inherited.media_box = Some(rect);
+    }
+    if let Some(rect) = dict.get("CropBox").and_then(|b| rect_from(file, b)) {
+        inherited.crop_box = Some(rect);
+    }
+    if let Some(rotate) = file.get_in(dict, "Rotate").as_int() {
+        inherited.rotate = Some(rotate);
+    }
+
+    if let Object::Array(kids) = file.get_in(dict, "Kids") {
+        if dict.name("Type") != Some("Page") {
+            for kid in kids.iter() {
+                if let Object::Ref(num, _) = kid {
+                    if !visited.insert(*num) {
+                        continue;
+                    }
+                }
+                let child = file.resolve(kid);
+                collect_pages(file, &child, inherited.clone(), depth + 1, visited, pages);
+            }
+            return;
+        }
+    }
+
+    let media = inherited.media_box.unwrap_or(DEFAULT_PAGE_BOX);
+    let bounds = match inherited.crop_box {
+        Some(crop) => {
+            let clipped = [
+                crop[0].max(media[0]),
+                crop[1].max(media[1]),
+                crop[2].min(media[2]),
+                crop[3].min(media[3]),
+            ];
+            if clipped[2] - clipped[0] >= 1.0 && clipped[3] - clipped[1] >= 1.0 {
+                clipped
+            } else {
+                media
+            }
+        }
+        None => media,
+    };
+    pages.push(Page {
+        resources: inherited.resources.unwrap_or_default(),
+        contents: dict.get("Contents").cloned().unwrap_or(Object::Null),
+        bounds,
+        rotate: inherited.rotate.unwrap_or(0).rem_euclid(360) as u32 / 90 * 90,
+    });
+}
+
+/// Page index (from 0) named by a `page=N` URL fragment.
+pub fn page_fragment(fragment: &str) -> usize {
+    fragment
+        .split('&')
+        .find_map(|part| part.strip_prefix("page="))
+        .and_then(|n| n.trim().parse::<usize>().ok())
+        .unwrap_or(1)
+        .saturating_sub(1)
+}
+
+/// Read a PDF from a directory for the shell commands and the viewer.
+pub fn open_file(dir_cluster: u32, name: &str) -> Result<Document, String> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let raw = fat
+        .read_file_in_dir(dir_cluster, name)
+        .map_err(|e| alloc::format!("{}: {}", name, e))?;
+    Document::parse(raw).map_err(|e| alloc::format!("{}: {}", name, e))
+}
+
+pub fn info_lines(name: &str, doc: &Document) -> Vec<String> {
+    let mut out = Vec::new();
+    out.push(alloc::format!("pdf: {}", name));
+    if let Some(title) = doc.title() {
+        out.push(alloc::format!("  titulo:  {}", ascii_text(title.as_str())));
+    }
+    out.push(alloc::format!("  paginas: {}", doc.page_count()));
+    if let Some((w, h)) = doc.page_size_pt(0) {
+        out.push(alloc::format!(
+            "  tamano:  {} x {} pt ({} x {} mm)",
+            w as u32,
+            h as u32,
+            (w * 25.4 / 72.0 + 0.5) as u32,
+            (h * 25.4 / 72.0 + 0.5) as u32
+        ));
+    }
+    out
+}
+
+/// `pdf info <archivo>` and `pdf text <archivo> [pagina]`. `pdf open` needs
+/// a window, so the desktop terminal handles it before calling this.
+pub fn command_lines(args: &str, dir_cluster: u32) -> Vec<String> {
+    let args = args.trim();
+    let (verb, rest) = args.split_once(' ').unwrap_or((args, ""));
+    let rest = rest.trim();
+    let mut out = Vec::new();
+    match (verb, rest) {
+        ("info", name) if !name.is_empty() => match open_file(dir_cluster, name) {
+            Ok(doc) => out.extend(info_lines(name, &doc)),
+            Err(e) => out.push(alloc::format!("pdf: {}", e)),
+        },
+        ("text", rest) if !rest.is_empty() => {
+            let (name, page) = match rest.rsplit_once(' ') {
+                Some((name, page)) if page.parse::<usize>().is_ok() => (name.trim(), page.parse::<usize>().ok()),
+                _ => (rest, None),
+            };
+            match open_file(dir_cluster, name) {
+                Ok(doc) => {
+                    let pages: Vec<usize> = match page {
+                        Some(n) if n >= 1 && n <= doc.page_count() => alloc::vec![n - 1],
+                        Some(_) => {
+                            out.push(alloc::format!("pdf: pagina fuera de rango (1-{})", doc.page_count()));
+                            return out;
+                        }
+                        None => (0..doc.page_count()).collect(),
+                    };
+                    for index in pages {
+                        if page.is_none() && doc.page_count() > 1 {
+                            out.push(alloc::format!("--- pagina {} ---", index + 1));
+                        }
+                        let text = doc.page_text(index).unwrap_or_default();
+                        if text.is_empty() {
+                            out.push(String::from("(sin texto)"));
+                        }
+                        out.extend(text.lines().map(ascii_text));
+                    }
+                }
+                Err(e) => out.push(alloc::format!("pdf: {}", e)),
+            }
+        }
+        ("open", _) => out.push(String::from(
+            "pdf: 'pdf open' solo esta disponible en la terminal del escritorio",
+        )),
+        _ => out.push(String::from(USAGE)),
+    }
+    out
+}
+
+const USAGE: &str = "uso: pdf info <archivo> | pdf text <archivo> [pagina] | pdf open <archivo|url>";
+
+/// One-page PDF with a blue square and a line of text, xref included.
+#[cfg(feature = "selftest")]
+fn sample_pdf(rotate: u32) -> Vec<u8> {
+    let content =
+        "0 0 1 rg 10 10 50 50 re f\nBT /F1 12 Tf 20 100 Td (Hola) Tj 0 -20 Td [(mun) -20 (do) -400 (bien)] TJ ET\n";
+    let objects = [
+        String::from("<< /Type /Catalog /Pages 2 0 R >>"),
+        alloc::format!(
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 /MediaBox [0 0 200 300] /Rotate {} >>",
+            rotate
+        ),
+        String::from("<< /Type /Page /Parent 2 0 R /Contents 4 0 R /Resources << /Font << /F1 5 0 R >> >> >>"),
+        alloc::format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content),
+        String::from("<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>"),
+    ];
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (i, body) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&alloc::format!("{} 0 obj\n{}\nendobj\n", i + 1, body));
+    }
+    let xref = pdf.len();
+    pdf.push_str(&alloc::format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1));
+    for offset in offsets {
+        pdf.push_str(&alloc::format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&alloc::format!(
+        "trailer\n<< /Size {} /Root 1 0 R /Info << /Title (Prueba) >> >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+    pdf.into_bytes()
+}
+
+crate::selftest::kernel_tests! {
+    "pdf";
+
+    fn page_renders_square_and_text() {
+        let doc = Document::parse(sample_pdf(0))?;
+        crate::selftest::ensure_eq(doc.page_count(), 1, "paginas")?;
+        crate::selftest::ensure_eq(doc.title(), Some(String::from("Prueba")), "titulo")?;
+        let page = doc.render_page(0, 100)?;
+        crate::selftest::ensure_eq((page.width, page.height), (200, 300), "tamano")?;
+        // PDF y grows upwards: the square sits 10..60 pt above the bottom.
+        crate::selftest::ensure_eq(page.pixels[265 * 200 + 35], 0x0000FF, "cuadrado")?;
+        crate::selftest::ensure_eq(page.pixels[5 * 200 + 5], 0xFFFFFF, "fondo")?;
+        crate::selftest::ensure_eq(page.text.as_str(), "Hola\nmundo bien", "texto")?;
+        crate::selftest::ensure_eq(page_fragment("page=3"), 2, "fragmento")?;
+        let half = doc.render_page(0, 50)?;
+        crate::selftest::ensure_eq((half.width, half.height), (100, 150), "zoom")
+    }
+
+    fn rotation_and_damaged_xref() {
+        let doc = Document::parse(sample_pdf(90))?;
+        crate::selftest::ensure_eq(doc.page_size_pt(0), Some((300.0, 200.0)), "rotada")?;
+        let page = doc.render_page(0, 100)?;
+        // Rotated clockwise, the square's corner at (10, 10) pt lands near the top left.
+        crate::selftest::ensure_eq(page.pixels[35 * 300 + 35], 0x0000FF, "cuadrado rotado")?;
+
+        let mut broken = sample_pdf(0);
+        let at = broken.windows(9).rposition(|w| w == b"startxref").ok_or("startxref")?;
+        broken.truncate(at);
+        broken.extend_from_slice(b"startxref\n99999\n%%EOF\n");
+        let doc = Document::parse(broken)?;
+        crate::selftest::ensure_eq(doc.page_text(0), Some(String::from("Hola\nmundo bien")), "recuperado")?;
+        crate::selftest::ensure(Document::parse(b"hola".to_vec()).is_err(), "no pdf")
+    }
+}
@@ -0,0 +1,942 @@
+//! PDF objects (ISO 32000-1 section 7.3), the tokenizer content streams
+//! share, and the cross-reference machinery that finds objects in a file.
+//!
+//! Classic `xref` tables, cross-reference streams and object streams are
+//! read, following `/Prev` through incremental updates. When the table is
+//! missing or points at garbage, the file is scanned for `n g obj` headers
+//! instead, the way most viewers recover damaged files.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use miniz_oxide::inflate::{decompress_to_vec_zlib_with_limit, TINFLStatus};
+
+/// Nesting limit for arrays and dictionaries.
+const MAX_DEPTH: usize = 64;
+/// `/Prev` hops followed before the chain is considered a loop.
+const MAX_XREF_SECTIONS: usize = 64;
+/// References followed to reach a direct object (`/Length 5 0 R` and such).
+const MAX_RESOLVE_DEPTH: usize = 8;
+/// Largest decoded stream accepted (content, fonts, images).
+pub const MAX_STREAM_BYTES: usize = 32 * 1024 * 1024;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Object {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Real(f32),
+    String(Vec<u8>),
+    Name(String),
+    Array(Vec<Object>),
+    Dict(Dict),
+    Stream(Stream),
+    Ref(u32, u16),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dict(pub Vec<(String, Object)>);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stream {
+    pub dict: Dict,
+    /// Bytes as stored in the file, filters not applied.
+    pub raw: Vec<u8>,
+}
+
+impl Dict {
+    pub fn get(&self, key: &str) -> Option<&Object> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn name(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(Object::as_name)
+    }
+}
+
+impl Object {
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Object::Int(v) => Some(*v as f32),
+            Object::Real(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Object::Int(v) => Some(*v),
+            Object::Real(v) => Some(*v as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_name(&self) -> Option<&str> {
+        match self {
+            Object::Name(name) => Some(name.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Object::String(bytes) => Some(bytes.as_slice()),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Object]> {
+        match self {
+            Object::Array(items) => Some(items.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// The dictionary of a dictionary or of a stream.
+    pub fn as_dict(&self) -> Option<&Dict> {
+        match self {
+            Object::Dict(dict) => Some(dict),
+            Object::Stream(stream) => Some(&stream.dict),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    Obj(Object),
+    /// Bare word: an operator in content streams, `obj`, `R`, `stream`...
+    /// in the file body.
+    Keyword(String),
+    ArrayOpen,
+    ArrayClose,
+    DictOpen,
+    DictClose,
+}
+
+fn is_white(b: u8) -> bool {
+    matches!(b, 0 | b'\t' | b'\n' | 0x0C | b'\r' | b' ')
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(b, b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%')
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+fn parse_number(word: &[u8]) -> Option<Object> {
+    let (negative, digits) = match word.first()? {
+        b'-' => (true, &word[1..]),
+        b'+' => (false, &word[1..]),
+        _ => (false, word),
+    };
+    let mut int = 0i64;
+    let mut fraction = 0f32;
+    let mut scale = 1f32;
+    let mut dot = false;
+    let mut any = false;
+    for &d in digits {
+        match d {
+            b'0'..=b'9' if dot => {
+                scale /= 10.0;
+                fraction += (d - b'0') as f32 * scale;
+                any = true;
+            }
+            b'0'..=b'9' => {
+                int = int.saturating_mul(10).saturating_add((d - b'0') as i64);
+                any = true;
+            }
+            b'.' if !dot => dot = true,
+            _ => return None,
+        }
+    }
+    if !any {
+        return None;
+    }
+    Some(match (dot, negative) {
+        (true, true) => Object::Real(-(int as f32 + fraction)),
+        (true, false) => Object::Real(int as f32 + fraction),
+        (false, true) => Object::Int(-int),
+        (false, false) => Object::Int(int),
+    })
+}
+
+pub struct Lexer<'a> {
+    pub data: &'a [u8],
+    pub pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    pub fn skip_space(&mut self) {
+        while let Some(&b) = self.data.get(self.pos) {
+            if is_white(b) {
+                self.pos += 1;
+            } else if b == b'%' {
+                while self.data.get(self.pos).is_some_and(|&c| c != b'\n' && c != b'\r') {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn regular(&mut self) -> &'a [u8] {
+        let start = self.pos;
+        while self
+            .data
+            .get(self.pos)
+            .is_some_and(|&b| !is_white(b) && !is_delimiter(b))
+        {
+            self.pos += 1;
+        }
+        &self.data[start..self.pos]
+    }
+
+    pub fn next(&mut self) -> Option<Token> {
+        self.skip_space();
+        let b = *self.data.get(self.pos)?;
+        let next = self.data.get(self.pos + 1).copied();
+        let token = match b {
+            b'[' => {
+                self.pos += 1;
+                Token::ArrayOpen
+            }
+            b']' => {
+                self.pos += 1;
+                Token::ArrayClose
+            }
+            b'<' if next == Some(b'<') => {
+                self.pos += 2;
+                Token::DictOpen
+            }
+            b'>' if next == Some(b'>') => {
+                self.pos += 2;
+                Token::DictClose
+            }
+            b'<' => Token::Obj(Object::String(self.hex_string())),
+            b'(' => Token::Obj(Object::String(self.literal_string())),
+            b'/' => {
+                self.pos += 1;
+                Token::Obj(Object::Name(self.name()))
+            }
+            b')' | b'>' | b'{' | b'}' => {
+                self.pos += 1;
+                Token::Keyword(String::from(b as char))
+            }
+            _ => {
+                let word = self.regular();
+                match word {
+                    b"true" => Token::Obj(Object::Bool(true)),
+                    b"false" => Token::Obj(Object::Bool(false)),
+                    b"null" => Token::Obj(Object::Null),
+                    _ => match parse_number(word) {
+                        Some(number) => Token::Obj(number),
+                        None => Token::Keyword(word.iter().map(|&c| c as char).collect()),
+                    },
+                }
+            }
+        };
+        Some(token)
+    }
+
+    fn name(&mut self) -> String {
+        let raw = self.regular();
+        let mut out = String::with_capacity(raw.len());
+        let mut i = 0usize;
+        while i < raw.len() {
+            let decoded = match (
+                raw[i],
+                raw.get(i + 1).and_then(|&b| hex_value(b)),
+                raw.get(i + 2).and_then(|&b| hex_value(b)),
+            ) {
+                (b'#', Some(hi), Some(lo)) => {
+                    i += 2;
+                    hi << 4 | lo
+                }
+                (b, _, _) => b,
+            };
+            out.push(decoded as char);
+            i += 1;
+        }
+        out
+    }
+
+    fn hex_string(&mut self) -> Vec<u8> {
+        let start = self.pos + 1;
+        let end = find(self.data, b">", start).unwrap_or(self.data.len());
+        self.pos = (end + 1).min(self.data.len());
+        ascii_hex_decode(&self.data[start.min(end)..end])
+    }
+
+    fn literal_string(&mut self) -> Vec<u8> {
+        self.pos += 1;
+        let mut out = Vec::new();
+        let mut depth = 0usize;
+        while let Some(&b) = self.data.get(self.pos) {
+            self.pos += 1;
+            match b {
+                b'(' => {
+                    depth += 1;
+                    out.push(b);
+                }
+                b')' if depth == 0 => break,
+                b')' => {
+                    depth -= 1;
+                    out.push(b);
+                }
+                b'\\' => {
+                    let Some(&e) = self.data.get(self.pos) else {
+                        break;
+                    };
+                    self.pos += 1;
+                    match e {
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b't' => out.push(b'\t'),
+                        b'b' => out.push(0x08),
+                        b'f' => out.push(0x0C),
+                        b'0'..=b'7' => {
+                            let mut v = (e - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.data.get(self.pos) {
+                                    Some(&d @ b'0'..=b'7') => {
+                                        v = v * 8 + (d - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            out.push(v as u8);
+                        }
+                        // Line continuation.
+                        b'\r' => {
+                            if self.data.get(self.pos) == Some(&b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        other => out.push(other),
+                    }
+                }
+                b'\r' => {
+                    if self.data.get(self.pos) == Some(&b'\n') {
+                        self.pos += 1;
+                    }
+                    out.push(b'\n');
+                }
+                _ => out.push(b),
+            }
+        }
+        out
+    }
+
+    /// Parse one object, recognising `n g R` references.
+    pub fn object(&mut self) -> Option<Object> {
+        let token = self.next()?;
+        self.object_from(token, 0)
+    }
+
+    pub fn object_from(&mut self, token: Token, depth: usize) -> Option<Object> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        match token {
+            Token::Obj(Object::Int(num)) => {
+                let save = self.pos;
+                if let Some(Token::Obj(Object::Int(generation))) = self.next() {
+                    if let Some(Token::Keyword(k)) = self.next() {
+                        if k == "R"
+                            && (0..=u32::MAX as i64).contains(&num)
+                            && (0..=u16::MAX as i64).contains(&generation)
+                        {
+                            return Some(Object::Ref(num as u32, generation as u16));
+                        }
+                    }
+                }
+                self.pos = save;
+                Some(Object::Int(num))
+            }
+            Token::Obj(obj) => Some(obj),
+            Token::ArrayOpen => {
+                let mut items = Vec::new();
+                loop {
+                    match self.next()? {
+                        Token::ArrayClose => break,
+                        token => items.push(self.object_from(token, depth + 1)?),
+                    }
+                }
+                Some(Object::Array(items))
+            }
+            Token::DictOpen => {
+                let mut dict = Dict::default();
+                loop {
+                    match self.next()? {
+                        Token::DictClose => break,
+                        Token::Obj(Object::Name(key)) => {
+                            let token = self.next()?;
+                            if token == Token::DictClose {
+                                break;
+                            }
+                            let value = self.object_from(token, depth + 1)?;
+                            dict.0.push((key, value));
+                        }
+                        // Junk between entries; skip it.
+                        _ => {}
+                    }
+                }
+                Some(Object::Dict(dict))
+            }
+            Token::ArrayClose | Token::DictClose | Token::Keyword(_) => Some(Object::Null),
+        }
+    }
+}
+
+/// Apply the PNG (10..=15) or TIFF (2) predictor named in `/DecodeParms`.
+fn unpredict(data: Vec<u8>, parms: Option<&Dict>) -> Result<Vec<u8>, &'static str> {
+    let Some(parms) = parms else {
+        return Ok(data);
+    };
+    let get = |key: &str, default: i64| parms.get(key).and_then(Object::as_int).unwrap_or(default);
+    let predictor = get("Predictor", 1);
+    if predictor < 2 {
+        return Ok(data);
+    }
+    let colors = get("Colors", 1).clamp(1, 32) as usize;
+    let bits = get("BitsPerComponent", 8).clamp(1, 16) as usize;
+    let columns = get("Columns", 1).clamp(1, 1 << 20) as usize;
+    let bpp = (colors * bits).div_ceil(8).max(1);
+    let row_len = (columns * colors * bits).div_ceil(8);
+    if predictor == 2 {
+        if bits != 8 {
+            return Err("predictor TIFF no soportado");
+        }
+        let mut data = data;
+        for row in data.chunks_mut(row_len) {
+            for i in bpp..row.len() {
+                row[i] = row[i].wrapping_add(row[i - bpp]);
+            }
+        }
+        return Ok(data);
+    }
+    let mut out = Vec::with_capacity(data.len());
+    let mut prev = alloc::vec![0u8; row_len];
+    for chunk in data.chunks(row_len + 1) {
+        if chunk.len() < 2 {
+            break;
+        }
+        let (filter, src) = (chunk[0], &chunk[1..]);
+        let mut row = alloc::vec![0u8; row_len];
+        for i in 0..src.len() {
+            let left = if i >= bpp { row[i - bpp] } else { 0 };
+            let up = prev[i];
+            let up_left = if i >= bpp { prev[i - bpp] } else { 0 };
+            row[i] = src[i].wrapping_add(match filter {
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => 0,
+            });
+        }
+        out.extend_from_slice(&row[..src.len()]);
+        prev = row;
+    }
+    Ok(out)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Hex digits up to `>`; whitespace is skipped and an odd last digit is
+/// padded with 0.
+fn ascii_hex_decode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() / 2);
+    let mut high: Option<u8> = None;
+    for &b in data.iter().take_while(|&&b| b != b'>') {
+        let Some(v) = hex_value(b) else {
+            continue;
+        };
+        match high.take() {
+            Some(h) => out.push(h << 4 | v),
+            None => high = Some(v),
+        }
+    }
+    if let Some(h) = high {
+        out.push(h << 4);
+    }
+    out
+}
+
+fn ascii85_decode(data: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let mut out = Vec::with_capacity(data.len() * 4 / 5);
+    let mut group = [0u32; 5];
+    let mut n = 0usize;
+    let data = data.strip_prefix(b"<~").unwrap_or(data);
+    for &b in data {
+        match b {
+            b'~' => break,
+            b'z' if n == 0 => out.extend_from_slice(&[0; 4]),
+            b'!'..=b'u' => {
+                group[n] = (b - b'!') as u32;
+                n += 1;
+                if n == 5 {
+                    let v = group.iter().fold(0u32, |acc, &d| acc.wrapping_mul(85).wrapping_add(d));
+                    out.extend_from_slice(&v.to_be_bytes());
+                    n = 0;
+                }
+            }
+            b if is_white(b) => {}
+            _ => return Err("ASCII85 invalido"),
+        }
+    }
+    if n > 1 {
+        for slot in group.iter_mut().skip(n) {
+            *slot = 84;
+        }
+        let v = group.iter().fold(0u32, |acc, &d| acc.wrapping_mul(85).wrapping_add(d));
+        out.extend_from_slice(&v.to_be_bytes()[..n - 1]);
+    }
+    Ok(out)
+}
+
+fn inflate(data: &[u8]) -> Result<Vec<u8>, &'static str> {
+    match decompress_to_vec_zlib_with_limit(data, MAX_STREAM_BYTES) {
+        Ok(out) => Ok(out),
+        // Truncated streams and bad Adler-32 sums are common; keep what
+        // inflated, as other viewers do.
+        Err(err) if err.status != TINFLStatus::HasMoreOutput && !err.output.is_empty() => Ok(err.output),
+        Err(_) => Err("flujo Flate invalido"),
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| i + from)
+}
+
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).rposition(|w| w == needle)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum XrefEntry {
+    Offset(usize),
+    /// Object stream number and index inside it.
+    Packed(u32, u32),
+}
+
+pub struct File {
+    data: Vec<u8>,
+    xref: BTreeMap<u32, XrefEntry>,
+    /// Objects unpacked from object streams.
+    packed: BTreeMap<u32, Object>,
+    pub trailer: Dict,
+}
+
+impl File {
+    pub fn open(data: Vec<u8>) -> Result<File, &'static str> {
+        let header = data.get(..data.len().min(1024)).unwrap_or(&[]);
+        if find(header, b"%PDF-", 0).is_none() {
+            return Err("no es un PDF");
+        }
+        let mut file = File {
+            data,
+            xref: BTreeMap::new(),
+            packed: BTreeMap::new(),
+            trailer: Dict::default(),
+        };
+        let chained = file.read_xref_chain();
+        if chained {
+            file.unpack_object_streams();
+        }
+        if !chained || !file.root_resolves() {
+            file.xref.clear();
+            file.packed.clear();
+            file.trailer = Dict::default();
+            file.scan_objects();
+            file.unpack_object_streams();
+            file.recover_catalog();
+        }
+        if file.trailer.get("Encrypt").is_some() {
+            return Err("PDF cifrado (no soportado)");
+        }
+        if !file.root_resolves() {
+            return Err("PDF sin catalogo");
+        }
+        Ok(file)
+    }
+
+    fn root_resolves(&self) -> bool {
+        let root = self.trailer.get("Root").map(|r| self.resolve(r));
+        matches!(root, Some(Object::Dict(_)))
+    }
+
+    fn read_xref_chain(&mut self) -> bool {
+        let tail_from = self.data.len().saturating_sub(4096);
+        let Some(at) = rfind(&self.data[tail_from..], b"startxref") else {
+            return false;
+        };
+        let mut lexer = Lexer::new(&self.data, tail_from + at + 9);
+        let Some(Token::Obj(Object::Int(mut offset))) = lexer.next() else {
+            return false;
+        };
+        let mut seen = BTreeSet::new();
+        let mut read_any = false;
+        while seen.len() < MAX_XREF_SECTIONS && offset >= 0 && seen.insert(offset) {
+            let Some(section) = self.read_xref_section(offset as usize) else {
+                break;
+            };
+            read_any = true;
+            // Hybrid files keep the compressed part in a separate stream.
+            if let Some(Object::Int(stm)) = section.get("XRefStm") {
+                if *stm >= 0 && seen.insert(*stm) {
+                    let _ = self.read_xref_section(*stm as usize);
+                }
+            }
+            for (key, value) in section.0.iter() {
+                if self.trailer.get(key).is_none() && key != "Prev" && key != "XRefStm" {
+                    self.trailer.0.push((key.clone(), value.clone()));
+                }
+            }
+            match section.get("Prev").and_then(Object::as_int) {
+                Some(prev) => offset = prev,
+                None => break,
+            }
+        }
+        read_any
+    }
+
+    /// Read the table or stream at `offset` into `xref`, keeping entries
+    /// already present (later updates win). Returns its trailer dictionary.
+    fn read_xref_section(&mut self, offset: usize) -> Option<Dict> {
+        let mut lexer = Lexer::new(&self.data, offset);
+        let first = lexer.next()?;
+        if first == Token::Keyword(String::from("xref")) {
+            let mut entries = Vec::new();
+            loop {
+                match lexer.next()? {
+                    Token::Keyword(k) if k == "trailer" => break,
+                    Token::Obj(Object::Int(start)) => {
+                        let Some(Token::Obj(Object::Int(count))) = lexer.next() else {
+                            return None;
+                        };
+                        for i in 0..count.max(0) {
+                            let (
+                                Some(Token::Obj(Object::Int(at))),
+                                Some(Token::Obj(Object::Int(_))),
+                                Some(Token::Keyword(kind)),
+                            ) = (lexer.next(), lexer.next(), lexer.next())
+                            else {
+                                return None;
+                            };
+                            if kind == "n" && at > 0 {
+                                entries.push(((start + i) as u32, XrefEntry::Offset(at as usize)));
+                            }
+                        }
+                    }
+                    _ => return None,
+                }
+            }
+            let trailer = match lexer.object()? {
+                Object::Dict(dict) => dict,
+                _ => return None,
+            };
+            for (num, entry) in entries {
+                self.xref.entry(num).or_insert(entry);
+            }
+            return Some(trailer);
+        }
+
+        // Cross-reference stream: `n g obj << /Type /XRef ... >> stream`.
+        let (_, object) = self.parse_indirect_at(offset, 0)?;
+        let Object::Stream(stream) = object else {
+            return None;
+        };
+        if stream.dict.name("Type") != Some("XRef") {
+            return None;
+        }
+        let data = self.decode_stream(&stream).ok()?;
+        let widths: Vec<usize> = stream
+            .dict
+            .get("W")?
+            .as_array()?
+            .iter()
+            .map(|w| w.as_int().unwrap_or(0).clamp(0, 8) as usize)
+            .collect();
+        if widths.len() < 3 {
+            return None;
+        }
+        let size = stream.dict.get("Size").and_then(Object::as_int).unwrap_or(0);
+        let index: Vec<i64> = match stream.dict.get("Index").and_then(Object::as_array) {
+            Some(items) => items.iter().filter_map(Object::as_int).collect(),
+            None => alloc::vec![0, size],
+        };
+        let row = widths[0] + widths[1] + widths[2];
+        let field = |bytes: &[u8]| bytes.iter().fold(0u64, |acc, &b| acc << 8 | b as u64);
+        let mut rows = data.chunks_exact(row.max(1));
+        for pair in index.chunks_exact(2) {
+            for i in 0..pair[1].max(0) {
+                let Some(entry) = rows.next() else {
+                    break;
+                };
+                let kind = if widths[0] == 0 { 1 } else { field(&entry[..widths[0]]) };
+                let a = field(&entry[widths[0]..widths[0] + widths[1]]);
+                let b = field(&entry[widths[0] + widths[1]..]);
+                let num = (pair[0] + i) as u32;
+                let value = match kind {
+                    1 => XrefEntry::Offset(a as usize),
+                    2 => XrefEntry::Packed(a as u32, b as u32),
+                    _ => continue,
+                };
+                self.xref.entry(num).or_insert(value);
+            }
+        }
+        Some(stream.dict)
+    }
+
+    /// Recovery: index every `n g obj` that starts a line and keep the last
+    /// `trailer` dictionary.
+    fn scan_objects(&mut self) {
+        let mut at = 0usize;
+        let len = self.data.len();
+        while at < len {
+            let line_start = at == 0 || matches!(self.data[at - 1], b'\n' | b'\r');
+            if line_start && self.data[at].is_ascii_digit() {
+                let mut lexer = Lexer::new(&self.data, at);
+                if let (Some(Token::Obj(Object::Int(num))), Some(Token::Obj(Object::Int(_))), Some(Token::Keyword(k))) =
+                    (lexer.next(), lexer.next(), lexer.next())
+                {
+                    if k == "obj" && (0..=u32::MAX as i64).contains(&num) {
+                        self.xref.insert(num as u32, XrefEntry::Offset(at));
+                    }
+                }
+            }
+            at += 1;
+        }
+        let mut from = 0usize;
+        while let Some(found) = find(&self.data, b"trailer", from) {
+            let mut lexer = Lexer::new(&self.data, found + 7);
+            if let Some(Object::Dict(dict)) = lexer.object() {
+                self.trailer = dict;
+            }
+            from = found + 7;
+        }
+    }
+
+    /// Without a usable trailer `/Root`, take any `/Type /Catalog`.
+    fn recover_catalog(&mut self) {
+        if !self.root_resolves() {
+            let catalog = self.xref.keys().copied().find(|num| {
+                self.get(*num)
+                    .as_ref()
+                    .and_then(Object::as_dict)
+                    .is_some_and(|d| d.name("Type") == Some("Catalog"))
+            });
+            if let Some(num) = catalog {
+                self.trailer.0.retain(|(k, _)| k != "Root");
+                self.trailer.0.push((String::from("Root"), Object::Ref(num, 0)));
+            }
+        }
+    }
+
+    fn unpack_object_streams(&mut self) {
+        let mut streams: BTreeSet<u32> = self
+            .xref
+            .values()
+            .filter_map(|e| match e {
+                XrefEntry::Packed(stream, _) => Some(*stream),
+                XrefEntry::Offset(_) => None,
+            })
+            .collect();
+        if streams.is_empty() {
+            // Recovered files: unpack every object stream found.
+            streams = self
+                .xref
+                .keys()
+                .copied()
+                .filter(|num| {
+                    matches!(self.get(*num), Some(Object::Stream(ref s)) if s.dict.name("Type") == Some("ObjStm"))
+                })
+                .collect();
+        }
+        for stream_num in streams {
+            let Some(Object::Stream(stream)) = self.get(stream_num) else {
+                continue;
+            };
+            let Ok(data) = self.decode_stream(&stream) else {
+                continue;
+            };
+            let count = stream.dict.get("N").and_then(Object::as_int).unwrap_or(0).max(0) as usize;
+            let first = stream.dict.get("First").and_then(Object::as_int).unwrap_or(0).max(0) as usize;
+            let mut header = Lexer::new(&data, 0);
+            let mut entries = Vec::new();
+            for _ in 0..count {
+                let (Some(Token::Obj(Object::Int(num))), Some(Token::Obj(Object::Int(at)))) =
+                    (header.next(), header.next())
+                else {
+                    break;
+                };
+                entries.push((num as u32, at.max(0) as usize));
+            }
+            for (index, (num, at)) in entries.into_iter().enumerate() {
+                let wanted = match self.xref.get(&num) {
+                    Some(XrefEntry::Packed(s, _)) => *s == stream_num,
+                    Some(XrefEntry::Offset(_)) => false,
+                    None => true,
+                };
+                if !wanted {
+                    continue;
+                }
+                let mut lexer = Lexer::new(&data, first + at);
+                if let Some(object) = lexer.object() {
+                    self.packed.insert(num, object);
+                    self.xref
+                        .entry(num)
+                        .or_insert(XrefEntry::Packed(stream_num, index as u32));
+                }
+            }
+        }
+    }
+
+    /// `n g obj <object> [stream ... endstream]` at `offset`.
+    fn parse_indirect_at(&self, offset: usize, depth: usize) -> Option<(u32, Object)> {
+        let mut lexer = Lexer::new(&self.data, offset);
+        let (Some(Token::Obj(Object::Int(num))), Some(Token::Obj(Object::Int(_))), Some(Token::Keyword(k))) =
+            (lexer.next(), lexer.next(), lexer.next())
+        else {
+            return None;
+        };
+        if k != "obj" {
+            return None;
+        }
+        let object = lexer.object()?;
+        let Object::Dict(dict) = object else {
+            return Some((num as u32, object));
+        };
+        if lexer.next() != Some(Token::Keyword(String::from("stream"))) {
+            return Some((num as u32, Object::Dict(dict)));
+        }
+        // The keyword is followed by CRLF or LF (a lone CR is tolerated).
+        let mut start = lexer.pos;
+        if self.data.get(start) == Some(&b'\r') {
+            start += 1;
+        }
+        if self.data.get(start) == Some(&b'\n') {
+            start += 1;
+        }
+        let declared = match dict.get("Length") {
+            Some(Object::Ref(n, _)) if depth < MAX_RESOLVE_DEPTH => {
+                self.get_depth(*n, depth + 1).and_then(|o| o.as_int())
+            }
+            Some(other) => other.as_int(),
+            None => None,
+        };
+        let exact = declared.and_then(|len| {
+            let end = start.checked_add(usize::try_from(len).ok()?)?;
+            let mut check = Lexer::new(&self.data, end);
+            check.skip_space();
+            self.data.get(check.pos..)?.starts_with(b"endstream").then_some(end)
+        });
+        let end = match exact {
+            Some(end) => end,
+            None => {
+                let mut end = find(&self.data, b"endstream", start)?;
+                while end > start && matches!(self.data[end - 1], b'\r' | b'\n') {
+                    end -= 1;
+                }
+                end
+            }
+        };
+        let raw = self.data[start..end].to_vec();
+        Some((num as u32, Object::Stream(Stream { dict, raw })))
+    }
+
+    pub fn get(&self, num: u32) -> Option<Object> {
+        self.get_depth(num, 0)
+    }
+
+    fn get_depth(&self, num: u32, depth: usize) -> Option<Object> {
+        match self.xref.get(&num)? {
+            XrefEntry::Offset(offset) => {
+                let (found, object) = self.parse_indirect_at(*offset, depth)?;
+                (found == num).then_some(object)
+            }
+            XrefEntry::Packed(..) => self.packed.get(&num).cloned(),
+        }
+    }
+
+    /// Follow references until a direct object; missing ones are `Null`.
+    pub fn resolve(&self, object: &Object) -> Object {
+        let mut current = object.clone();
+        for _ in 0..MAX_RESOLVE_DEPTH {
+            match current {
+                Object::Ref(num, _) => current = self.get(num).unwrap_or(Object::Null),
+                other => return other,
+            }
+        }
+        Object::Null
+    }
+
+    /// `dict[key]` with references followed.
+    pub fn get_in(&self, dict: &Dict, key: &str) -> Object {
+        dict.get(key).map(|v| self.resolve(v)).unwrap_or(Object::Null)
+    }
+
+    /// Apply the stream's filters. Image codecs (DCT, JBIG2, CCITT, JPX)
+    /// come back as errors so callers can draw a placeholder instead.
+    pub fn decode_stream(&self, stream: &Stream) -> Result<Vec<u8>, &'static str> {
+        let filters: Vec<String> = match self.get_in(&stream.dict, "Filter") {
+            Object::Name(name) => alloc::vec![name],
+            Object::Array(items) => items
+                .iter()
+                .filter_map(|f| self.resolve(f).as_name().map(String::from))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let parms: Vec<Option<Dict>> = match self.get_in(&stream.dict, "DecodeParms") {
+            Object::Dict(dict) => alloc::vec![Some(dict)],
+            Object::Array(items) => items.iter().map(|p| self.resolve(p).as_dict().cloned()).collect(),
+            _ => Vec::new(),
+        };
+        let mut data = stream.raw.clone();
+        for (i, filter) in filters.iter().enumerate() {
+            data = match filter.as_str() {
+                "FlateDecode" | "Fl" => unpredict(inflate(&data)?, parms.get(i).and_then(Option::as_ref))?,
+                "ASCIIHexDecode" | "AHx" => ascii_hex_decode(&data),
+                "ASCII85Decode" | "A85" => ascii85_decode(&data)?,
+                "DCTDecode" | "DCT" => return Err("imagen JPEG"),
+                "JPXDecode" | "JBIG2Decode" | "CCITTFaxDecode" | "CCF" => return Err("codec de imagen no soportado"),
+                _ => return Err("filtro no soportado"),
+            };
+            if data.len() > MAX_STREAM_BYTES {
+                return Err("flujo demasiado grande");
+            }
+        }
+        Ok(data)
+    }
+}
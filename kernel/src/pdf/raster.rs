@@ -0,0 +1,481 @@
+//! Anti-aliased scanline filling for PDF paths and TrueType glyphs.
+//!
+//! Paths are flattened to polygons in device pixels and filled with the
+//! nonzero or even-odd rule at 4x4 samples per pixel. Strokes become one
+//! quad per segment (square ends cover the joins) filled the same way.
+
+use alloc::vec::Vec;
+
+/// Samples per pixel along each axis.
+const SUBSAMPLES: i32 = 4;
+/// Segments a curve is split into, at most.
+const MAX_CURVE_STEPS: usize = 32;
+/// Upper bound on the edges of one fill, so a hostile path cannot stall.
+const MAX_EDGES: usize = 200_000;
+/// Image pixel left untouched by `Canvas::draw_image` (unpainted mask bits).
+pub const TRANSPARENT: u32 = 0xFF00_0000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Matrix {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+pub fn abs(v: f32) -> f32 {
+    if v < 0.0 {
+        -v
+    } else {
+        v
+    }
+}
+
+pub fn floor(v: f32) -> f32 {
+    let t = v as i64 as f32;
+    if t > v {
+        t - 1.0
+    } else {
+        t
+    }
+}
+
+pub fn sqrt(v: f32) -> f32 {
+    if v <= 0.0 {
+        return 0.0;
+    }
+    let mut x = f32::from_bits((v.to_bits() >> 1) + 0x1FC0_0000);
+    for _ in 0..3 {
+        x = 0.5 * (x + v / x);
+    }
+    x
+}
+
+impl Matrix {
+    pub const IDENTITY: Matrix = Matrix::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0);
+
+    pub const fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Matrix {
+        Matrix { a, b, c, d, e, f }
+    }
+
+    pub fn translate(x: f32, y: f32) -> Matrix {
+        Matrix::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    /// `self` first, then `other`; PDF's `cm` is `m.then(&ctm)`.
+    pub fn then(&self, o: &Matrix) -> Matrix {
+        Matrix {
+            a: self.a * o.a + self.b * o.c,
+            b: self.a * o.b + self.b * o.d,
+            c: self.c * o.a + self.d * o.c,
+            d: self.c * o.b + self.d * o.d,
+            e: self.e * o.a + self.f * o.c + o.e,
+            f: self.e * o.b + self.f * o.d + o.f,
+        }
+    }
+
+    pub fn apply(&self, x: f32, y: f32) -> Point {
+        Point {
+            x: x * self.a + y * self.c + self.e,
+            y: x * self.b + y * self.d + self.f,
+        }
+    }
+
+    pub fn invert(&self) -> Option<Matrix> {
+        let det = self.a * self.d - self.b * self.c;
+        if abs(det) < 1e-9 {
+            return None;
+        }
+        let (a, b, c, d) = (self.d / det, -self.b / det, -self.c / det, self.a / det);
+        Some(Matrix {
+            a,
+            b,
+            c,
+            d,
+            e: -(self.e * a + self.f * c),
+            f: -(self.e * b + self.f * d),
+        })
+    }
+
+    /// How much lengths grow, on average: turns line widths into pixels.
+    pub fn scale(&self) -> f32 {
+        sqrt(abs(self.a * self.d - self.b * self.c))
+    }
+}
+
+/// Integer rectangle in device pixels, `x1`/`y1` exclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Clip {
+    pub x0: i32,
+    pub y0: i32,
+    pub x1: i32,
+    pub y1: i32,
+}
+
+impl Clip {
+    pub fn intersect(&self, o: &Clip) -> Clip {
+        Clip {
+            x0: self.x0.max(o.x0),
+            y0: self.y0.max(o.y0),
+            x1: self.x1.min(o.x1),
+            y1: self.y1.min(o.y1),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x0 >= self.x1 || self.y0 >= self.y1
+    }
+
+    /// Pixels touched by `points`.
+    pub fn around(points: impl Iterator<Item = Point>) -> Clip {
+        let mut clip = Clip {
+            x0: i32::MAX,
+            y0: i32::MAX,
+            x1: i32::MIN,
+            y1: i32::MIN,
+        };
+        for p in points {
+            let (x, y) = (floor(p.x) as i32, floor(p.y) as i32);
+            clip.x0 = clip.x0.min(x);
+            clip.y0 = clip.y0.min(y);
+            clip.x1 = clip.x1.max(x + 1);
+            clip.y1 = clip.y1.max(y + 1);
+        }
+        clip
+    }
+}
+
+/// A path being built: closed or open polylines in device pixels.
+#[derive(Clone, Debug, Default)]
+pub struct Path {
+    pub contours: Vec<(Vec<Point>, bool)>,
+}
+
+fn curve_steps(points: &[Point]) -> usize {
+    let mut length = 0f32;
+    for pair in points.windows(2) {
+        length += abs(pair[1].x - pair[0].x) + abs(pair[1].y - pair[0].y);
+    }
+    ((length / 3.0) as usize).clamp(2, MAX_CURVE_STEPS)
+}
+
+impl Path {
+    pub fn is_empty(&self) -> bool {
+        self.contours.iter().all(|(c, _)| c.is_empty())
+    }
+
+    pub fn current(&self) -> Option<Point> {
+        self.contours.last().and_then(|(c, _)| c.last().copied())
+    }
+
+    pub fn move_to(&mut self, p: Point) {
+        match self.contours.last_mut() {
+            Some((c, closed)) if c.len() <= 1 && !*closed => {
+                c.clear();
+                c.push(p);
+            }
+            _ => self.contours.push((alloc::vec![p], false)),
+        }
+    }
+
+    pub fn line_to(&mut self, p: Point) {
+        match self.contours.last_mut() {
+            Some((c, false)) => c.push(p),
+            _ => {
+                // After `h` or with no current point, start where it ended.
+                let start = self.contours.last().and_then(|(c, _)| c.first().copied()).unwrap_or(p);
+                self.contours.push((alloc::vec![start, p], false));
+            }
+        }
+    }
+
+    pub fn cubic_to(&mut self, c1: Point, c2: Point, p: Point) {
+        let p0 = self.current().unwrap_or(c1);
+        let steps = curve_steps(&[p0, c1, c2, p]);
+        for i in 1..=steps {
+            let t = i as f32 / steps as f32;
+            let u = 1.0 - t;
+            let (w0, w1, w2, w3) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+            self.line_to(Point {
+                x: w0 * p0.x + w1 * c1.x + w2 * c2.x + w3 * p.x,
+                y: w0 * p0.y + w1 * c1.y + w2 * c2.y + w3 * p.y,
+            });
+        }
+    }
+
+    pub fn quad_to(&mut self, c: Point, p: Point) {
+        let p0 = self.current().unwrap_or(c);
+        let steps = curve_steps(&[p0, c, p]);
+        for i in 1..=steps {
+            let t = i as f32 / steps as f32;
+            let u = 1.0 - t;
+            let (w0, w1, w2) = (u * u, 2.0 * u * t, t * t);
+            self.line_to(Point {
+                x: w0 * p0.x + w1 * c.x + w2 * p.x,
+                y: w0 * p0.y + w1 * c.y + w2 * p.y,
+            });
+        }
+    }
+
+    pub fn close(&mut self) {
+        if let Some((_, closed)) = self.contours.last_mut() {
+            *closed = true;
+        }
+    }
+
+    pub fn bounds(&self) -> Clip {
+        Clip::around(self.contours.iter().flat_map(|(c, _)| c.iter().copied()))
+    }
+}
+
+pub struct Canvas {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u32>,
+}
+
+#[derive(Clone, Copy)]
+struct Edge {
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    winding: i32,
+}
+
+fn blend(dst: u32, src: u32, alpha: u32) -> u32 {
+    if alpha >= 255 {
+        return src;
+    }
+    let mix = |shift: u32| {
+        let d = (dst >> shift) & 0xFF;
+        let s = (src >> shift) & 0xFF;
+        ((s * alpha + d * (255 - alpha)) / 255) << shift
+    };
+    mix(16) | mix(8) | mix(0)
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32, background: u32) -> Canvas {
+        Canvas {
+            width,
+            height,
+            pixels: alloc::vec![background; width as usize * height as usize],
+        }
+    }
+
+    pub fn bounds(&self) -> Clip {
+        Clip {
+            x0: 0,
+            y0: 0,
+            x1: self.width as i32,
+            y1: self.height as i32,
+        }
+    }
+
+    /// Fill `path` (every contour closed) with `color`.
+    pub fn fill(&mut self, path: &Path, even_odd: bool, clip: &Clip, color: u32) {
+        let area = path.bounds().intersect(clip).intersect(&self.bounds());
+        if area.is_empty() {
+            return;
+        }
+        let mut edges = Vec::new();
+        for (contour, _) in path.contours.iter() {
+            let n = contour.len();
+            if n < 2 {
+                continue;
+            }
+            for i in 0..n {
+                let (p, q) = (contour[i], contour[(i + 1) % n]);
+                if p.y == q.y {
+                    continue;
+                }
+                edges.push(if p.y < q.y {
+                    Edge {
+                        x0: p.x,
+                        y0: p.y,
+                        x1: q.x,
+                        y1: q.y,
+                        winding: 1,
+                    }
+                } else {
+                    Edge {
+                        x0: q.x,
+                        y0: q.y,
+                        x1: p.x,
+                        y1: p.y,
+                        winding: -1,
+                    }
+                });
+            }
+            if edges.len() > MAX_EDGES {
+                return;
+            }
+        }
+        edges.sort_unstable_by(|a, b| a.y0.partial_cmp(&b.y0).unwrap_or(core::cmp::Ordering::Equal));
+
+        let width = (area.x1 - area.x0) as usize;
+        let mut coverage = alloc::vec![0u16; width];
+        let mut active: Vec<Edge> = Vec::new();
+        let mut crossings: Vec<(f32, i32)> = Vec::new();
+        let mut next = 0usize;
+        let sub_x0 = area.x0 * SUBSAMPLES;
+        let sub_x1 = area.x1 * SUBSAMPLES;
+        for y in area.y0..area.y1 {
+            coverage.iter_mut().for_each(|c| *c = 0);
+            let mut touched = false;
+            for s in 0..SUBSAMPLES {
+                let sy = y as f32 + (s as f32 + 0.5) / SUBSAMPLES as f32;
+                while next < edges.len() && edges[next].y0 <= sy {
+                    active.push(edges[next]);
+                    next += 1;
+                }
+                active.retain(|e| e.y1 > sy);
+                crossings.clear();
+                for e in active.iter().filter(|e| e.y0 <= sy) {
+                    let x = e.x0 + (sy - e.y0) * (e.x1 - e.x0) / (e.y1 - e.y0);
+                    crossings.push((x, e.winding));
+                }
+                crossings.sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(core::cmp::Ordering::Equal));
+                let mut winding = 0i32;
+                for i in 0..crossings.len() {
+                    winding += crossings[i].1;
+                    let inside = if even_odd { winding & 1 != 0 } else { winding != 0 };
+                    if !inside || i + 1 >= crossings.len() {
+                        continue;
+                    }
+                    let start = ((crossings[i].0 * SUBSAMPLES as f32 + 0.5) as i32).clamp(sub_x0, sub_x1);
+                    let end = ((crossings[i + 1].0 * SUBSAMPLES as f32 + 0.5) as i32).clamp(sub_x0, sub_x1);
+                    if start < end {
+                        touched = true;
+                        add_span(&mut coverage, start - sub_x0, end - sub_x0);
+                    }
+                }
+            }
+            if !touched {
+                continue;
+            }
+            let row = y as usize * self.width as usize;
+            let full = (SUBSAMPLES * SUBSAMPLES) as u32;
+            for (i, &c) in coverage.iter().enumerate() {
+                if c == 0 {
+                    continue;
+                }
+                let index = row + area.x0 as usize + i;
+                let alpha = (c as u32).min(full) * 255 / full;
+                self.pixels[index] = blend(self.pixels[index], color, alpha);
+            }
+        }
+    }
+
+    /// Stroke every segment of `path` `width` pixels wide.
+    pub fn stroke(&mut self, path: &Path, width: f32, clip: &Clip, color: u32) {
+        let half = width.max(1.0) / 2.0;
+        let mut quads = Path::default();
+        for (contour, closed) in path.contours.iter() {
+            let n = contour.len();
+            let segments = if *closed { n } else { n.saturating_sub(1) };
+            for i in 0..segments {
+                let (p, q) = (contour[i], contour[(i + 1) % n]);
+                let (dx, dy) = (q.x - p.x, q.y - p.y);
+                let len = sqrt(dx * dx + dy * dy);
+                if len < 1e-3 {
+                    continue;
+                }
+                let (ux, uy) = (dx / len * half, dy / len * half);
+                let (p, q) = (
+                    Point {
+                        x: p.x - ux,
+                        y: p.y - uy,
+                    },
+                    Point {
+                        x: q.x + ux,
+                        y: q.y + uy,
+                    },
+                );
+                quads.contours.push((
+                    alloc::vec![
+                        Point {
+                            x: p.x - uy,
+                            y: p.y + ux
+                        },
+                        Point {
+                            x: q.x - uy,
+                            y: q.y + ux
+                        },
+                        Point {
+                            x: q.x + uy,
+                            y: q.y - ux
+                        },
+                        Point {
+                            x: p.x + uy,
+                            y: p.y - ux
+                        },
+                    ],
+                    true,
+                ));
+            }
+        }
+        self.fill(&quads, false, clip, color);
+    }
+
+    /// Draw `width` x `height` pixels (row 0 on top) into the unit square
+    /// mapped by `m`, the way PDF image XObjects are placed.
+    pub fn draw_image(&mut self, m: &Matrix, width: u32, height: u32, pixels: &[u32], clip: &Clip) {
+        let Some(inverse) = m.invert() else {
+            return;
+        };
+        if width == 0 || height == 0 || pixels.len() < width as usize * height as usize {
+            return;
+        }
+        let corners = [
+            m.apply(0.0, 0.0),
+            m.apply(1.0, 0.0),
+            m.apply(0.0, 1.0),
+            m.apply(1.0, 1.0),
+        ];
+        let area = Clip::around(corners.into_iter())
+            .intersect(clip)
+            .intersect(&self.bounds());
+        if area.is_empty() {
+            return;
+        }
+        for y in area.y0..area.y1 {
+            for x in area.x0..area.x1 {
+                let p = inverse.apply(x as f32 + 0.5, y as f32 + 0.5);
+                if !(0.0..1.0).contains(&p.x) || !(0.0..1.0).contains(&p.y) {
+                    continue;
+                }
+                let sx = ((p.x * width as f32) as u32).min(width - 1);
+                let sy = (((1.0 - p.y) * height as f32) as u32).min(height - 1);
+                let pixel = pixels[sy as usize * width as usize + sx as usize];
+                if pixel != TRANSPARENT {
+                    self.pixels[y as usize * self.width as usize + x as usize] = pixel;
+                }
+            }
+        }
+    }
+}
+
+/// Add one sample row covering subpixels `start..end` (relative to the
+/// row's first pixel) to `coverage`.
+fn add_span(coverage: &mut [u16], start: i32, end: i32) {
+    let (first, last) = ((start / SUBSAMPLES) as usize, ((end - 1) / SUBSAMPLES) as usize);
+    if first == last {
+        coverage[first] += (end - start) as u16;
+        return;
+    }
+    coverage[first] += (SUBSAMPLES - start % SUBSAMPLES) as u16;
+    for c in coverage[first + 1..last].iter_mut() {
+        *c += SUBSAMPLES as u16;
+    }
+    coverage[last] += (end - last as i32 * SUBSAMPLES) as u16;
+}
@@ -0,0 +1,379 @@
+//! Desktop frame timing: a microsecond view of the monotonic clock, the
+//! per-phase breakdown of each frame, the paint rate cap and the numbers the performance HUD shows.
+//!
+//! The GUI loop records input/net/layout around its own steps; the
+//! compositor records paint and `framebuffer::present` records present.
+//! Averages are published twice a second so the HUD stays readable.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use crate::config::ConfigValue;
+use crate::spinlock::SpinLock;
+
+/// Paint rate cap in frames per second; 0 paints on every change.
+pub const FPS_CAP_KEY: &str = "desktop.fps_cap";
+pub const FPS_CAP_MIN: u32 = 15;
+pub const FPS_CAP_MAX: u32 = 240;
+const PUBLISH_INTERVAL_US: u64 = 500_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Phase {
+    /// Mouse, keyboard and gamepad polling and event dispatch.
+    Input,
+    /// `net::poll`.
+    Net,
+    /// Window state updates (`service_background_tasks`).
+    Layout,
+    /// Drawing into the back buffer.
+    Paint,
+    /// Copy (or upscale) to the panel.
+    Present,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 5] = [Phase::Input, Phase::Net, Phase::Layout, Phase::Paint, Phase::Present];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Phase::Input => "input",
+            Phase::Net => "net",
+            Phase::Layout => "layout",
+            Phase::Paint => "paint",
+            Phase::Present => "present",
+        }
+    }
+}
+
+static HUD_VISIBLE: AtomicBool = AtomicBool::new(false);
+/// Cap used while `desktop.fps_cap` is unset: the panel refresh rate.
+static DEFAULT_CAP: AtomicU32 = AtomicU32::new(60);
+static CONFIG_CAP: AtomicU32 = AtomicU32::new(u32::MAX);
+
+/// Microseconds on the monotonic clock (`clocksource::monotonic_ns`).
+pub fn now_us() -> u64 {
+    crate::clocksource::monotonic_ns() / 1000
+}
+
+/// Window of frames being accumulated and the averages last published.
+struct FrameStats {
+    sums: [u64; 5],
+    frames: u32,
+    window_start_us: u64,
+    worst_frame_us: u64,
+    frame_start_us: u64,
+    last_paint_us: u64,
+    published: Published,
+    net_last: (u64, u64),
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Published {
+    pub fps: u32,
+    /// Average per painted frame, in microseconds.
+    pub phase_us: [u32; 5],
+    pub worst_frame_us: u32,
+    pub rx_bytes_per_s: u64,
+    pub tx_bytes_per_s: u64,
+}
+
+impl FrameStats {
+    const fn new() -> Self {
+        Self {
+            sums: [0; 5],
+            frames: 0,
+            window_start_us: 0,
+            worst_frame_us: 0,
+            frame_start_us: 0,
+            last_paint_us: 0,
+            published: Published {
+                fps: 0,
+                phase_us: [0; 5],
+                worst_frame_us: 0,
+                rx_bytes_per_s: 0,
+                tx_bytes_per_s: 0,
+            },
+            net_last: (0, 0),
+        }
+    }
+
+    /// Close the window at `now`; returns false while it is still open.
+    fn publish(&mut self, now: u64, net: (u64, u64)) -> bool {
+        let elapsed = now.saturating_sub(self.window_start_us);
+        if elapsed < PUBLISH_INTERVAL_US {
+            return false;
+        }
+        let frames = self.frames.max(1) as u64;
+        let mut phase_us = [0u32; 5];
+        for (out, sum) in phase_us.iter_mut().zip(self.sums.iter()) {
+            *out = (sum / frames).min(u32::MAX as u64) as u32;
+        }
+        let per_second = |delta: u64| delta.saturating_mul(1_000_000) / elapsed;
+        self.published = Published {
+            fps: ((self.frames as u64 * 1_000_000 + elapsed / 2) / elapsed) as u32,
+            phase_us,
+            worst_frame_us: self.worst_frame_us.min(u32::MAX as u64) as u32,
+            rx_bytes_per_s: per_second(net.0.saturating_sub(self.net_last.0)),
+            tx_bytes_per_s: per_second(net.1.saturating_sub(self.net_last.1)),
+        };
+        self.net_last = net;
+        self.sums = [0; 5];
+        self.frames = 0;
+        self.worst_frame_us = 0;
+        self.window_start_us = now;
+        true
+    }
+}
+
+static STATS: SpinLock<FrameStats> = SpinLock::new(FrameStats::new());
+
+/// Add the time since `start_us` to `phase` for the current frame.
+pub fn record(phase: Phase, start_us: u64) {
+    let elapsed = now_us().saturating_sub(start_us);
+    let mut stats = STATS.lock();
+    stats.sums[phase as usize] = stats.sums[phase as usize].saturating_add(elapsed);
+}
+
+/// Start of a GUI loop iteration.
+pub fn begin_frame() -> u64 {
+    let now = now_us();
+    STATS.lock().frame_start_us = now;
+    now
+}
+
+/// End of a GUI loop iteration. Returns true when new averages were
+/// published, so the caller can repaint the HUD.
+pub fn end_frame(painted: bool) -> bool {
+    let now = now_us();
+    let net = crate::net::traffic_bytes();
+    let mut stats = STATS.lock();
+    if stats.window_start_us == 0 {
+        stats.window_start_us = now;
+        stats.net_last = net;
+    }
+    if painted {
+        stats.frames += 1;
+        let frame = now.saturating_sub(stats.frame_start_us);
+        stats.worst_frame_us = stats.worst_frame_us.max(frame);
+        stats.last_paint_us = now;
+    }
+    stats.publish(now, net)
+}
+
+pub fn published() -> Published {
+    STATS.lock().published
+}
+
+/// Effective paint cap; 0 means uncapped.
+pub fn fps_cap() -> u32 {
+    match CONFIG_CAP.load(Ordering::Acquire) {
+        u32::MAX => DEFAULT_CAP.load(Ordering::Acquire),
+        cap => cap,
+    }
+}
+
+/// Minimum time between paints for `cap`; 0 when uncapped.
+pub fn paint_interval_us(cap: u32) -> u64 {
+    if cap == 0 {
+        0
+    } else {
+        1_000_000 / cap.clamp(FPS_CAP_MIN, FPS_CAP_MAX) as u64
+    }
+}
+
+/// Whether enough time passed since the last paint under the cap. A pending
+/// repaint simply waits for a later loop iteration.
+pub fn paint_due() -> bool {
+    let interval = paint_interval_us(fps_cap());
+    interval == 0 || now_us().saturating_sub(STATS.lock().last_paint_us) >= interval
+}
+
+fn parse_cap(value: Option<&ConfigValue>) -> u32 {
+    let cap = match value {
+        Some(ConfigValue::Int(n)) => *n,
+        Some(ConfigValue::Str(text)) if text == "off" => 0,
+        Some(ConfigValue::Str(text)) => match text.parse::<i64>() {
+            Ok(n) => n,
+            Err(_) => return u32::MAX,
+        },
+        _ => return u32::MAX,
+    };
+    match cap {
+        0 => 0,
+        n if n > 0 => (n as u32).clamp(FPS_CAP_MIN, FPS_CAP_MAX),
+        _ => u32::MAX,
+    }
+}
+
+fn on_cap_changed(_key: &str, value: Option<&ConfigValue>) {
+    CONFIG_CAP.store(parse_cap(value), Ordering::Release);
+}
+
+/// Follow `desktop.fps_cap` in the config registry.
+pub fn init() {
+    crate::config::watch(FPS_CAP_KEY, on_cap_changed);
+}
+
+/// Desktop entry: default the cap to the panel refresh and pick up the
+/// stored cap.
+pub fn start_desktop(refresh_hz: u32) {
+    DEFAULT_CAP.store(refresh_hz.clamp(FPS_CAP_MIN, FPS_CAP_MAX), Ordering::Release);
+    on_cap_changed(FPS_CAP_KEY, crate::config::get(FPS_CAP_KEY).as_ref());
+}
+
+pub fn hud_visible() -> bool {
+    HUD_VISIBLE.load(Ordering::Acquire)
+}
+
+pub fn set_hud_visible(visible: bool) {
+    HUD_VISIBLE.store(visible, Ordering::Release);
+}
+
+pub fn toggle_hud() -> bool {
+    !HUD_VISIBLE.fetch_xor(true, Ordering::AcqRel)
+}
+
+fn format_ms(us: u32) -> String {
+    alloc::format!("{}.{}", us / 1000, (us % 1000) / 100)
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    crate::i18n::format::size(bytes)
+}
+
+pub fn format_rate(bytes_per_s: u64) -> String {
+    alloc::format!("{}/s", format_bytes(bytes_per_s))
+}
+
+fn cap_label(cap: u32) -> String {
+    if cap == 0 {
+        String::from("sin limite")
+    } else {
+        alloc::format!("{} fps", cap)
+    }
+}
+
+/// Lines of the on-screen HUD.
+pub fn hud_lines() -> Vec<String> {
+    let p = published();
+    let frame_us: u32 = p.phase_us.iter().fold(0u32, |acc, us| acc.saturating_add(*us));
+    let mut breakdown = String::new();
+    for phase in Phase::ALL {
+        if !breakdown.is_empty() {
+            breakdown.push(' ');
+        }
+        breakdown.push_str(alloc::format!("{} {}", phase.name(), format_ms(p.phase_us[phase as usize])).as_str());
+    }
+    let heap_used = crate::allocator::heap_used_bytes() / (1024 * 1024);
+    let heap_size = crate::allocator::heap_size_bytes() / (1024 * 1024);
+    alloc::vec![
+        alloc::format!(
+            "{} FPS  frame {} ms (max {})  cap {}",
+            p.fps,
+            format_ms(frame_us),
+            format_ms(p.worst_frame_us),
+            cap_label(fps_cap())
+        ),
+        alloc::format!("{} ms", breakdown),
+        alloc::format!("heap {}/{} MiB", heap_used, heap_size),
+        alloc::format!(
+            "net rx {} tx {}",
+            format_rate(p.rx_bytes_per_s),
+            format_rate(p.tx_bytes_per_s)
+        ),
+    ]
+}
+
+/// Shared implementation of `perf [status|hud on|off|cap <fps>|off|auto]`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim();
+    let mut out = Vec::new();
+    match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [] | ["status"] => {
+            out.extend(hud_lines());
+            let (queued, dropped) = crate::gui::event_queue::stats();
+            out.push(alloc::format!("Cola de eventos: {} pendientes, {} descartados", queued, dropped));
+            out.push(alloc::format!(
+                "HUD {} (F12). Limite: {}",
+                if hud_visible() { "visible" } else { "oculto" },
+                cap_label(fps_cap())
+            ));
+        }
+        ["hud"] => {
+            let visible = toggle_hud();
+            out.push(alloc::format!("HUD {}", if visible { "visible" } else { "oculto" }));
+        }
+        ["hud", "on"] | ["hud", "off"] => {
+            set_hud_visible(args.ends_with("on"));
+            out.push(alloc::format!(
+                "HUD {}",
+                if hud_visible() { "visible" } else { "oculto" }
+            ));
+        }
+        ["cap", "auto"] => match crate::config::unset(FPS_CAP_KEY) {
+            Ok(_) => out.push(alloc::format!("Limite: {} (refresco del panel)", cap_label(fps_cap()))),
+            Err(e) => out.push(alloc::format!("config: {}", e)),
+        },
+        ["cap", value] => {
+            let cap = if *value == "off" {
+                Some(0)
+            } else {
+                value.parse::<u32>().ok()
+            };
+            match cap {
+                Some(cap) if cap == 0 || (FPS_CAP_MIN..=FPS_CAP_MAX).contains(&cap) => {
+                    match crate::config::set(FPS_CAP_KEY, ConfigValue::Int(cap as i64)) {
+                        Ok(()) => out.push(alloc::format!("Limite: {}", cap_label(cap))),
+                        Err(e) => out.push(alloc::format!("config: {}", e)),
+                    }
+                }
+                _ => out.push(alloc::format!(
+                    "perf: limite entre {} y {} fps, off o auto",
+                    FPS_CAP_MIN,
+                    FPS_CAP_MAX
+                )),
+            }
+        }
+        _ => out.push(String::from("Uso: perf [status|hud [on|off]|cap <fps>|off|auto]")),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "perf";
+
+    fn publish_averages_per_painted_frame() {
+        let mut stats = FrameStats::new();
+        stats.window_start_us = 1_000_000;
+        stats.sums[Phase::Paint as usize] = 30_000;
+        stats.sums[Phase::Input as usize] = 3_000;
+        stats.frames = 30;
+        stats.worst_frame_us = 4_500;
+        crate::selftest::ensure(!stats.publish(1_200_000, (0, 0)), "ventana abierta")?;
+        crate::selftest::ensure(stats.publish(1_500_000, (51_200, 1_024)), "ventana cerrada")?;
+        let p = stats.published;
+        crate::selftest::ensure_eq(p.fps, 60, "fps")?;
+        crate::selftest::ensure_eq(p.phase_us[Phase::Paint as usize], 1_000, "paint")?;
+        crate::selftest::ensure_eq(p.phase_us[Phase::Input as usize], 100, "input")?;
+        crate::selftest::ensure_eq(p.worst_frame_us, 4_500, "peor frame")?;
+        crate::selftest::ensure_eq(p.rx_bytes_per_s, 102_400, "rx")?;
+        crate::selftest::ensure_eq(stats.frames, 0, "reinicio")
+    }
+
+    fn cap_parsing_and_interval() {
+        crate::selftest::ensure_eq(parse_cap(Some(&ConfigValue::Int(0))), 0, "0 = sin limite")?;
+        crate::selftest::ensure_eq(parse_cap(Some(&ConfigValue::Int(1000))), FPS_CAP_MAX, "tope")?;
+        crate::selftest::ensure_eq(parse_cap(Some(&ConfigValue::Str(String::from("off")))), 0, "off")?;
+        crate::selftest::ensure_eq(parse_cap(None), u32::MAX, "sin clave")?;
+        crate::selftest::ensure_eq(paint_interval_us(60), 16_666, "60 fps")?;
+        crate::selftest::ensure_eq(paint_interval_us(0), 0, "sin limite")
+    }
+
+    fn rates_are_human_readable() {
+        crate::selftest::ensure_eq(format_rate(512).as_str(), "512 B/s", "bytes")?;
+        crate::selftest::ensure_eq(format_rate(1536).as_str(), "1.5 KiB/s", "KiB")?;
+        crate::selftest::ensure_eq(format_rate(3 * 1024 * 1024).as_str(), "3.0 MiB/s", "MiB")?;
+        crate::selftest::ensure_eq(format_ms(16_666).as_str(), "16.6", "ms")
+    }
+}
@@ -0,0 +1,757 @@
+//! Owners, groups and Unix modes for files on FAT32/exFAT volumes.
+//!
+//! Neither filesystem stores ownership, so explicit records (from `chmod`,
+//! `chown` or an entry created by a regular user) live in the
+//! `crate::fsmeta` sidecar. Every other entry gets a default from where it
+//! sits: the volume root is `root:usuarios 0775`, system folders
+//! (`SYSTEM_DIRS`) are `root:root 0755`, `TRASH` is `1777` (anyone may drop
+//! entries, only their owner takes them out), `HOME/<usuario>` is private to
+//! that user, and anything else inherits from its folder without the execute
+//! bits for files. (A filesystem with owners of its own, such as ext4,
+//! would answer `entry_perm` from its inodes; the kernel mounts none yet.)
+//!
+//! The checks live in the public `Fat32` mutators and `read_file_in_dir`,
+//! so every path that opens, reads, writes, renames or deletes goes through
+//! them. They apply to the credential the current CPU acts as (`act_as`):
+//! terminal commands, their queued copies and ring 3 act as the session user
+//! (`su`); kernel services act as root, which skips every check.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::fat32::Fat32;
+use crate::fs::{DirEntry, FileType};
+use crate::spinlock::SpinLock;
+
+pub const READ: u16 = 0o4;
+pub const WRITE: u16 = 0o2;
+pub const EXEC: u16 = 0o1;
+pub const STICKY: u16 = 0o1000;
+const MODE_MASK: u16 = 0o1777;
+
+/// Top-level folders only root may change.
+const SYSTEM_DIRS: [&str; 4] = ["REDUXOS", "EFI", "APPS", "LINUXRT"];
+const TRASH_DIR: &str = "TRASH";
+const HOME_DIR: &str = "HOME";
+/// Deepest folder whose permissions are worked out; below it the folder
+/// falls back to the root default.
+const DEPTH_MAX: usize = 64;
+const CACHE_MAX: usize = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Cred {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl Cred {
+    pub const ROOT: Cred = Cred { uid: 0, gid: 0 };
+    pub const NOBODY: Cred = Cred { uid: 65534, gid: 65534 };
+
+    pub fn is_root(&self) -> bool {
+        self.uid == 0
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Perm {
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u16,
+}
+
+impl Perm {
+    const ROOT_DIR: Perm = Perm {
+        uid: 0,
+        gid: 100,
+        mode: 0o775,
+    };
+
+    /// Whether `cred` has every bit of `want` (`READ | WRITE | EXEC`).
+    pub fn allows(&self, cred: Cred, want: u16) -> bool {
+        if cred.is_root() {
+            return true;
+        }
+        let bits = if cred.uid == self.uid {
+            self.mode >> 6
+        } else if cred.gid == self.gid {
+            self.mode >> 3
+        } else {
+            self.mode
+        };
+        bits & want == want
+    }
+
+    pub fn sticky(&self) -> bool {
+        self.mode & STICKY != 0
+    }
+
+    /// `drwxr-xr-x` style, with `t`/`T` for the sticky bit.
+    pub fn text(&self, is_dir: bool) -> String {
+        let mut out = String::with_capacity(10);
+        out.push(if is_dir { 'd' } else { '-' });
+        for shift in [6u16, 3, 0] {
+            let bits = self.mode >> shift;
+            out.push(if bits & READ != 0 { 'r' } else { '-' });
+            out.push(if bits & WRITE != 0 { 'w' } else { '-' });
+            let exec = bits & EXEC != 0;
+            out.push(match (shift == 0 && self.sticky(), exec) {
+                (true, true) => 't',
+                (true, false) => 'T',
+                (false, true) => 'x',
+                (false, false) => '-',
+            });
+        }
+        out
+    }
+}
+
+pub struct User {
+    pub name: &'static str,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl User {
+    pub fn cred(&self) -> Cred {
+        Cred {
+            uid: self.uid,
+            gid: self.gid,
+        }
+    }
+}
+
+pub const USERS: [User; 4] = [
+    User {
+        name: "root",
+        uid: 0,
+        gid: 0,
+    },
+    User {
+        name: "usuario",
+        uid: 1000,
+        gid: 100,
+    },
+    User {
+        name: "invitado",
+        uid: 1001,
+        gid: 1001,
+    },
+    User {
+        name: "nobody",
+        uid: 65534,
+        gid: 65534,
+    },
+];
+
+pub const GROUPS: [(&str, u32); 4] = [("root", 0), ("usuarios", 100), ("invitado", 1001), ("nogroup", 65534)];
+
+/// User by name or numeric uid.
+pub fn lookup_user(text: &str) -> Option<&'static User> {
+    let uid = text.parse::<u32>().ok();
+    USERS.iter().find(|u| u.name == text || Some(u.uid) == uid)
+}
+
+/// Group id by name or number.
+pub fn lookup_group(text: &str) -> Option<u32> {
+    GROUPS
+        .iter()
+        .find(|(name, _)| *name == text)
+        .map(|(_, gid)| *gid)
+        .or_else(|| text.parse::<u32>().ok())
+}
+
+pub fn user_name(uid: u32) -> String {
+    match USERS.iter().find(|u| u.uid == uid) {
+        Some(user) => String::from(user.name),
+        None => alloc::format!("{}", uid),
+    }
+}
+
+pub fn group_name(gid: u32) -> String {
+    match GROUPS.iter().find(|(_, g)| *g == gid) {
+        Some((name, _)) => String::from(*name),
+        None => alloc::format!("{}", gid),
+    }
+}
+
+static SESSION: SpinLock<Cred> = SpinLock::new(Cred::ROOT);
+/// Credential each CPU acts as; `None` is root.
+static ACTING: SpinLock<[Option<Cred>; crate::smp::MAX_CPUS]> = SpinLock::new([None; crate::smp::MAX_CPUS]);
+
+/// The user logged in at the desktop and the terminal.
+pub fn session() -> Cred {
+    *SESSION.lock()
+}
+
+pub fn set_session(cred: Cred) {
+    *SESSION.lock() = cred;
+    crate::klog::log(
+        crate::klog::Level::Info,
+        alloc::format!("perm: sesion como {}", user_name(cred.uid)).as_str(),
+    );
+}
+
+/// Who filesystem calls on this CPU are checked against.
+pub fn acting() -> Cred {
+    ACTING.lock()[crate::smp::current_cpu_index()].unwrap_or(Cred::ROOT)
+}
+
+/// Restores the previous credential of its CPU when dropped.
+pub struct Acting {
+    cpu: usize,
+    previous: Option<Cred>,
+}
+
+impl Drop for Acting {
+    fn drop(&mut self) {
+        ACTING.lock()[self.cpu] = self.previous;
+    }
+}
+
+/// Act as `cred` on this CPU until the guard is dropped.
+pub fn act_as(cred: Cred) -> Acting {
+    let cpu = crate::smp::current_cpu_index();
+    let previous = ACTING.lock()[cpu].replace(cred);
+    Acting { cpu, previous }
+}
+
+/// Where a folder sits, for the defaults that depend on it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Role {
+    Root,
+    Home,
+    Other,
+}
+
+/// Default permissions of `name` inside a folder with `parent` permissions
+/// and `role`.
+fn inherit(parent: &Perm, role: Role, name: &str, is_dir: bool) -> Perm {
+    let dir_or_file = |uid: u32, gid: u32, dir_mode: u16| Perm {
+        uid,
+        gid,
+        mode: if is_dir { dir_mode } else { dir_mode & 0o666 },
+    };
+    if role == Role::Root {
+        if SYSTEM_DIRS.iter().any(|d| d.eq_ignore_ascii_case(name)) || name.eq_ignore_ascii_case(HOME_DIR) {
+            return dir_or_file(0, 0, 0o755);
+        }
+        if is_dir && name.eq_ignore_ascii_case(TRASH_DIR) {
+            return Perm {
+                uid: 0,
+                gid: 0,
+                mode: STICKY | 0o777,
+            };
+        }
+    }
+    if role == Role::Home && is_dir {
+        if let Some(user) = USERS.iter().find(|u| u.name.eq_ignore_ascii_case(name)) {
+            return Perm {
+                uid: user.uid,
+                gid: user.gid,
+                mode: 0o700,
+            };
+        }
+    }
+    if parent.sticky() {
+        // Shared drop folder: others may not write what it holds.
+        return dir_or_file(parent.uid, parent.gid, 0o755);
+    }
+    dir_or_file(parent.uid, parent.gid, parent.mode & 0o777)
+}
+
+/// Folder permissions worked out so far, per volume.
+static CACHE: SpinLock<Vec<(u64, u32, Perm, Role)>> = SpinLock::new(Vec::new());
+
+pub fn init() {
+    crate::fswatch::subscribe(on_fs_event);
+}
+
+fn on_fs_event(event: &crate::fswatch::Event) {
+    use crate::fswatch::Change;
+    if matches!(
+        event.change,
+        Change::Removed | Change::MovedFrom | Change::MovedTo | Change::Overflow
+    ) {
+        CACHE.lock().retain(|(volume, ..)| *volume != event.volume);
+    }
+}
+
+fn forget(fat: &Fat32) {
+    let volume = fat.volume_id();
+    CACHE.lock().retain(|(v, ..)| *v != volume);
+}
+
+fn find_entry(fat: &mut Fat32, dir: u32, name: &str) -> Option<DirEntry> {
+    let entries = fat.read_dir_entries(dir).ok()?;
+    entries
+        .into_iter()
+        .find(|e| e.valid && (e.matches_name(name) || e.full_name().eq_ignore_ascii_case(name)))
+}
+
+fn norm(fat: &Fat32, dir: u32) -> u32 {
+    if dir < 2 {
+        fat.root_cluster
+    } else {
+        dir
+    }
+}
+
+/// Permissions and role of folder `dir`.
+fn dir_info(fat: &mut Fat32, dir: u32) -> (Perm, Role) {
+    let root = fat.root_cluster;
+    let volume = fat.volume_id();
+    let dir = norm(fat, dir);
+    // Folders from `dir` up to the first one known, with their name in
+    // their parent.
+    let mut chain: Vec<(u32, u32, String)> = Vec::new();
+    let mut at = dir;
+    let mut known = None;
+    while known.is_none() {
+        if at == root {
+            known = Some((Perm::ROOT_DIR, Role::Root));
+            break;
+        }
+        if let Some(&(_, _, perm, role)) = CACHE.lock().iter().find(|(v, d, ..)| *v == volume && *d == at) {
+            known = Some((perm, role));
+            break;
+        }
+        if chain.len() >= DEPTH_MAX {
+            known = Some((Perm::ROOT_DIR, Role::Other));
+            break;
+        }
+        let parent = match find_entry(fat, at, "..") {
+            Some(e) if e.cluster >= 2 => e.cluster,
+            _ => root,
+        };
+        let name = fat
+            .read_dir_entries(parent)
+            .ok()
+            .and_then(|entries| {
+                entries
+                    .into_iter()
+                    .find(|e| e.valid && e.file_type == FileType::Directory && e.cluster == at)
+                    .map(|e| e.full_name())
+            })
+            .unwrap_or_default();
+        chain.push((at, parent, name));
+        at = parent;
+    }
+    let (mut perm, mut role) = known.unwrap_or((Perm::ROOT_DIR, Role::Root));
+    while let Some((cluster, parent, name)) = chain.pop() {
+        let explicit = crate::fsmeta::perm_of(fat, parent, &name);
+        let next_role = if role == Role::Root && name.eq_ignore_ascii_case(HOME_DIR) {
+            Role::Home
+        } else {
+            Role::Other
+        };
+        perm = explicit.unwrap_or_else(|| inherit(&perm, role, &name, true));
+        role = next_role;
+        let mut cache = CACHE.lock();
+        if cache.len() >= CACHE_MAX {
+            cache.remove(0);
+        }
+        cache.push((volume, cluster, perm, role));
+    }
+    (perm, role)
+}
+
+/// Permissions of folder `dir` itself.
+pub fn dir_perm(fat: &mut Fat32, dir: u32) -> Perm {
+    dir_info(fat, dir).0
+}
+
+/// Permissions of the entry `name` of folder `dir`.
+pub fn entry_perm(fat: &mut Fat32, dir: u32, name: &str, is_dir: bool) -> Perm {
+    let dir = norm(fat, dir);
+    if let Some(perm) = crate::fsmeta::perm_of(fat, dir, name) {
+        return perm;
+    }
+    let (parent, role) = dir_info(fat, dir);
+    inherit(&parent, role, name, is_dir)
+}
+
+const DENIED: &str = "Permiso denegado";
+
+fn require(perm: &Perm, cred: Cred, want: u16) -> Result<(), &'static str> {
+    if perm.allows(cred, want) {
+        Ok(())
+    } else {
+        Err(DENIED)
+    }
+}
+
+/// Reading the file `name` of `dir`.
+pub fn authorize_read(fat: &mut Fat32, dir: u32, name: &str) -> Result<(), &'static str> {
+    let cred = acting();
+    if cred.is_root() {
+        return Ok(());
+    }
+    require(&dir_perm(fat, dir), cred, EXEC)?;
+    match find_entry(fat, dir, name) {
+        Some(entry) => require(
+            &entry_perm(fat, dir, &entry.full_name(), entry.file_type == FileType::Directory),
+            cred,
+            READ,
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Reading `name` of `dir` through its links, for callers that then read
+/// the file by cluster.
+pub fn authorize_open(fat: &mut Fat32, dir: u32, name: &str) -> Result<(), &'static str> {
+    if acting().is_root() {
+        return Ok(());
+    }
+    let place = crate::fsmeta::resolve(fat, dir, name, true)?;
+    authorize_read(fat, place.dir, &place.name)
+}
+
+/// Listing or watching folder `dir`.
+pub fn authorize_list(fat: &mut Fat32, dir: u32) -> Result<(), &'static str> {
+    let cred = acting();
+    if cred.is_root() {
+        return Ok(());
+    }
+    require(&dir_perm(fat, dir), cred, READ | EXEC)
+}
+
+/// Adding an entry to folder `dir`.
+pub fn authorize_create(fat: &mut Fat32, dir: u32) -> Result<(), &'static str> {
+    let cred = acting();
+    if cred.is_root() {
+        return Ok(());
+    }
+    require(&dir_perm(fat, dir), cred, WRITE | EXEC)
+}
+
+/// Writing the file `name` of `dir`, creating it if needed. `Ok(true)` when
+/// a regular user creates it, so `note_created` must record the owner.
+pub fn authorize_write(fat: &mut Fat32, dir: u32, name: &str) -> Result<bool, &'static str> {
+    let cred = acting();
+    if cred.is_root() {
+        return Ok(false);
+    }
+    match find_entry(fat, dir, name) {
+        Some(entry) => {
+            require(&dir_perm(fat, dir), cred, EXEC)?;
+            require(&entry_perm(fat, dir, &entry.full_name(), false), cred, WRITE)?;
+            Ok(false)
+        }
+        None => authorize_create(fat, dir).map(|_| true),
+    }
+}
+
+/// Deleting, renaming or moving away the entry `name` of `dir`; in a sticky
+/// folder only the owner of the entry or of the folder may.
+pub fn authorize_remove(fat: &mut Fat32, dir: u32, name: &str) -> Result<(), &'static str> {
+    let cred = acting();
+    if cred.is_root() {
+        return Ok(());
+    }
+    let parent = dir_perm(fat, dir);
+    require(&parent, cred, WRITE | EXEC)?;
+    if parent.sticky() && parent.uid != cred.uid {
+        if let Some(entry) = find_entry(fat, dir, name) {
+            let perm = entry_perm(fat, dir, &entry.full_name(), entry.file_type == FileType::Directory);
+            if perm.uid != cred.uid {
+                return Err(DENIED);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Emptying folder `dir`: like removing each entry.
+pub fn authorize_empty(fat: &mut Fat32, dir: u32) -> Result<(), &'static str> {
+    let cred = acting();
+    if cred.is_root() {
+        return Ok(());
+    }
+    let names: Vec<String> = fat
+        .read_dir_entries(dir)
+        .map(|entries| {
+            entries
+                .iter()
+                .filter(|e| e.valid && !e.matches_name(".") && !e.matches_name(".."))
+                .map(|e| e.full_name())
+                .collect()
+        })
+        .unwrap_or_default();
+    if names.is_empty() {
+        return authorize_create(fat, dir);
+    }
+    for name in names.iter() {
+        authorize_remove(fat, dir, name)?;
+    }
+    Ok(())
+}
+
+/// Record a regular user as the owner of the entry they just created, or
+/// moved into a sticky folder.
+pub fn note_created(fat: &mut Fat32, dir: u32, name: &str, is_dir: bool) {
+    let cred = acting();
+    if cred.is_root() {
+        return;
+    }
+    let dir = norm(fat, dir);
+    let (parent, role) = dir_info(fat, dir);
+    let mode = inherit(&parent, role, name, is_dir).mode;
+    let perm = Perm {
+        uid: cred.uid,
+        gid: cred.gid,
+        mode: if parent.sticky() { mode & !0o022 } else { mode },
+    };
+    if let Err(e) = crate::fsmeta::set_perm(fat, dir, name, perm) {
+        crate::klog::log(
+            crate::klog::Level::Warning,
+            alloc::format!("perm: {}: {}", name, e).as_str(),
+        );
+    }
+    if is_dir {
+        forget(fat);
+    }
+}
+
+/// Record the owner of `name` after a regular user moved it into `dir`, if
+/// `dir` is sticky and the entry has no owner of its own yet.
+pub fn note_moved(fat: &mut Fat32, dir: u32, name: &str) {
+    if acting().is_root() {
+        return;
+    }
+    let dir = norm(fat, dir);
+    if !dir_perm(fat, dir).sticky() || crate::fsmeta::perm_of(fat, dir, name).is_some() {
+        return;
+    }
+    if let Some(entry) = find_entry(fat, dir, name) {
+        note_created(fat, dir, &entry.full_name(), entry.file_type == FileType::Directory);
+    }
+}
+
+/// Octal mode as `chmod` takes it: up to four digits.
+pub fn parse_mode(text: &str) -> Option<u16> {
+    if text.is_empty() || text.len() > 4 {
+        return None;
+    }
+    u16::from_str_radix(text, 8).ok().filter(|m| *m & !MODE_MASK == 0)
+}
+
+/// Change the mode of `name` in `dir`: its owner or root.
+pub fn chmod(fat: &mut Fat32, dir: u32, name: &str, is_dir: bool, mode: u16) -> Result<(), &'static str> {
+    let cred = acting();
+    let mut perm = entry_perm(fat, dir, name, is_dir);
+    if !cred.is_root() && cred.uid != perm.uid {
+        return Err("Operacion no permitida");
+    }
+    perm.mode = mode & MODE_MASK;
+    crate::fsmeta::set_perm(fat, dir, name, perm)?;
+    forget(fat);
+    Ok(())
+}
+
+/// Change owner and/or group of `name` in `dir`: root only.
+pub fn chown(
+    fat: &mut Fat32,
+    dir: u32,
+    name: &str,
+    is_dir: bool,
+    uid: Option<u32>,
+    gid: Option<u32>,
+) -> Result<(), &'static str> {
+    if !acting().is_root() {
+        return Err("Operacion no permitida");
+    }
+    let mut perm = entry_perm(fat, dir, name, is_dir);
+    perm.uid = uid.unwrap_or(perm.uid);
+    perm.gid = gid.unwrap_or(perm.gid);
+    crate::fsmeta::set_perm(fat, dir, name, perm)?;
+    forget(fat);
+    Ok(())
+}
+
+/// `id`, `whoami` and `su [usuario]`.
+pub fn session_lines(verb: &str, args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    match verb {
+        "whoami" => out.push(user_name(session().uid)),
+        "id" => {
+            let cred = session();
+            out.push(alloc::format!(
+                "uid={}({}) gid={}({})",
+                cred.uid,
+                user_name(cred.uid),
+                cred.gid,
+                group_name(cred.gid)
+            ));
+        }
+        _ => {
+            let name = args.trim();
+            let name = if name.is_empty() { "root" } else { name };
+            match lookup_user(name) {
+                Some(user) => {
+                    set_session(user.cred());
+                    out.push(alloc::format!("sesion: {} (uid={})", user.name, user.uid));
+                }
+                None => out.push(alloc::format!("su: usuario desconocido: {}", name)),
+            }
+        }
+    }
+    out
+}
+
+/// `chmod <modo> <ruta>`, `chown <usuario>[:grupo] <ruta>` and
+/// `stat <ruta>`, with paths relative to the terminal folder `cwd`.
+pub fn command_lines(fat: &mut Fat32, cwd: u32, verb: &str, args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    if fat.init_status != crate::fat32::InitStatus::Success {
+        out.push(String::from("sin volumen montado"));
+        return out;
+    }
+    let args: Vec<&str> = args.split_whitespace().collect();
+    let (spec, path) = match (verb, args.as_slice()) {
+        ("stat", [path]) => ("", *path),
+        ("chmod" | "chown", [spec, path]) => (*spec, *path),
+        _ => {
+            out.push(String::from(match verb {
+                "chmod" => "uso: chmod <modo octal> <ruta>",
+                "chown" => "uso: chown <usuario>[:grupo] <ruta>",
+                _ => "uso: stat <ruta>",
+            }));
+            return out;
+        }
+    };
+    let place = match crate::fsmeta::resolve(fat, cwd, path, true) {
+        Ok(place) => place,
+        Err(e) => {
+            out.push(alloc::format!("{}: {}", verb, e));
+            return out;
+        }
+    };
+    // The folder a path such as "." or "/" names is an entry of its parent.
+    let (dir, name, is_dir) = match place.entry {
+        Some(entry) => (place.dir, entry.full_name(), entry.file_type == FileType::Directory),
+        None if place.name.is_empty() && norm(fat, place.dir) != fat.root_cluster => {
+            let dir = norm(fat, place.dir);
+            let parent = match find_entry(fat, dir, "..") {
+                Some(e) if e.cluster >= 2 => e.cluster,
+                _ => fat.root_cluster,
+            };
+            let name = fat.read_dir_entries(parent).ok().and_then(|entries| {
+                entries
+                    .into_iter()
+                    .find(|e| e.valid && e.cluster == dir)
+                    .map(|e| e.full_name())
+            });
+            match name {
+                Some(name) => (parent, name, true),
+                None => {
+                    out.push(alloc::format!("{}: {} no existe", verb, path));
+                    return out;
+                }
+            }
+        }
+        None if place.name.is_empty() => {
+            if verb == "stat" {
+                out.push(alloc::format!(
+                    "{}  {}:{}  /",
+                    Perm::ROOT_DIR.text(true),
+                    "root",
+                    "usuarios"
+                ));
+            } else {
+                out.push(alloc::format!("{}: la raiz del volumen no se puede cambiar", verb));
+            }
+            return out;
+        }
+        None => {
+            out.push(alloc::format!("{}: {} no existe", verb, path));
+            return out;
+        }
+    };
+    let result = match verb {
+        "stat" => Ok(()),
+        "chmod" => match parse_mode(spec) {
+            Some(mode) => chmod(fat, dir, &name, is_dir, mode),
+            None => Err("modo invalido (octal, p. ej. 755 o 1777)"),
+        },
+        _ => {
+            let (user, group) = match spec.split_once(':') {
+                Some((user, group)) => (user, Some(group)),
+                None => (spec, None),
+            };
+            let uid = if user.is_empty() {
+                Ok(None)
+            } else {
+                lookup_user(user).map(|u| Some(u.uid)).ok_or("usuario desconocido")
+            };
+            let gid = match group {
+                Some(group) if !group.is_empty() => lookup_group(group).map(Some).ok_or("grupo desconocido"),
+                _ => Ok(None),
+            };
+            uid.and_then(|uid| gid.map(|gid| (uid, gid)))
+                .and_then(|(uid, gid)| chown(fat, dir, &name, is_dir, uid, gid))
+        }
+    };
+    match result {
+        Ok(()) => {
+            let perm = entry_perm(fat, dir, &name, is_dir);
+            out.push(alloc::format!(
+                "{}  {}:{}  {}",
+                perm.text(is_dir),
+                user_name(perm.uid),
+                group_name(perm.gid),
+                place.path
+            ));
+        }
+        Err(e) => out.push(alloc::format!("{}: {}", verb, e)),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "perm";
+
+    fn mode_bits_by_class() {
+        let perm = Perm { uid: 1000, gid: 100, mode: 0o750 };
+        let owner = Cred { uid: 1000, gid: 100 };
+        let group = Cred { uid: 1001, gid: 100 };
+        let other = Cred { uid: 1001, gid: 1001 };
+        crate::selftest::ensure(perm.allows(owner, READ | WRITE | EXEC), "propietario")?;
+        crate::selftest::ensure(perm.allows(group, READ | EXEC), "grupo lee")?;
+        crate::selftest::ensure(!perm.allows(group, WRITE), "grupo no escribe")?;
+        crate::selftest::ensure(!perm.allows(other, READ), "otros no leen")?;
+        crate::selftest::ensure(perm.allows(Cred::ROOT, WRITE), "root")?;
+        crate::selftest::ensure_eq(perm.text(false), String::from("-rwxr-x---"), "texto")?;
+        crate::selftest::ensure_eq(
+            Perm { uid: 0, gid: 0, mode: 0o1777 }.text(true),
+            String::from("drwxrwxrwt"),
+            "sticky",
+        )
+    }
+
+    fn defaults_by_place() {
+        let root = Perm::ROOT_DIR;
+        let system = inherit(&root, Role::Root, "reduxos", true);
+        crate::selftest::ensure_eq(system, Perm { uid: 0, gid: 0, mode: 0o755 }, "sistema")?;
+        let trash = inherit(&root, Role::Root, "TRASH", true);
+        crate::selftest::ensure(trash.sticky() && trash.allows(Cred::NOBODY, WRITE), "papelera")?;
+        let home = inherit(&root, Role::Root, "HOME", true);
+        let mine = inherit(&home, Role::Home, "usuario", true);
+        crate::selftest::ensure_eq(mine, Perm { uid: 1000, gid: 100, mode: 0o700 }, "home")?;
+        crate::selftest::ensure(!mine.allows(USERS[2].cred(), READ), "home privado")?;
+        let doc = inherit(&root, Role::Root, "notas.txt", false);
+        crate::selftest::ensure_eq(doc.mode, 0o664, "archivo sin x")?;
+        let trashed = inherit(&trash, Role::Other, "viejo.txt", false);
+        crate::selftest::ensure_eq(trashed.mode, 0o644, "sticky sin escritura ajena")
+    }
+
+    fn parse_modes_and_users() {
+        crate::selftest::ensure_eq(parse_mode("755"), Some(0o755), "755")?;
+        crate::selftest::ensure_eq(parse_mode("1777"), Some(0o1777), "1777")?;
+        crate::selftest::ensure_eq(parse_mode("4755"), None, "setuid")?;
+        crate::selftest::ensure_eq(parse_mode("89"), None, "no octal")?;
+        crate::selftest::ensure_eq(lookup_user("1000").map(|u| u.name), Some("usuario"), "uid")?;
+        crate::selftest::ensure_eq(lookup_group("usuarios"), Some(100), "grupo")
+    }
+}
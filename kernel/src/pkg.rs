@@ -0,0 +1,1243 @@
+//! reduxpkg: package manager over an HTTPS repository index.
+//!
+//! The repository is a text index (`REDUX-PKG-INDEX-V1`) with one `[name]`
+//! section per package version. Packages are tar, tar.gz or zip archives;
+//! each download is checked against the index `SIZE`/`SHA256` with the same
+//! REDUX-SIG-V1 verifier the `install` command applies to `.sig` files, then
+//! extracted into `APPS/<DIR>`. Installed packages are tracked in
+//! `PKG/DB.TXT`; the post-install hook writes one root `.APP` launcher per
+//! `APP=` line, which the Start menu picks up.
+//!
+//! ```text
+//! REDUX-PKG-INDEX-V1
+//! [hello]
+//! VERSION=1.2.0
+//! URL=hello-1.2.0.tar.gz
+//! SIZE=10240
+//! SHA256=<64 hex>
+//! DEPENDS=libui >= 0.3, fonts
+//! SUMMARY=Hola mundo
+//! APP=Hello|runapp {DIR}/MAIN.RML
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use sha2::{Digest, Sha256};
+
+use crate::archive::Archive;
+use crate::fs::FileType;
+
+const PKG_DIR: &str = "PKG";
+const PKG_REPO_FILE: &str = "REPO.CFG";
+const PKG_INDEX_FILE: &str = "INDEX.TXT";
+const PKG_DB_FILE: &str = "DB.TXT";
+const PKG_APPS_DIR: &str = "APPS";
+const PKG_INDEX_HEADER: &str = "redux-pkg-index-v1";
+const PKG_DB_HEADER: &str = "REDUX-PKG-DB-V1";
+/// Optional metadata member at the archive root, same keys as the index.
+const PKG_INFO_MEMBER: &str = ".PKGINFO";
+const PKG_MAX_REDIRECTS: usize = 3;
+const PKG_MAX_APPS: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VersionOp {
+    Eq,
+    Ge,
+    Gt,
+    Le,
+    Lt,
+}
+
+impl VersionOp {
+    fn as_str(self) -> &'static str {
+        match self {
+            VersionOp::Eq => "=",
+            VersionOp::Ge => ">=",
+            VersionOp::Gt => ">",
+            VersionOp::Le => "<=",
+            VersionOp::Lt => "<",
+        }
+    }
+}
+
+/// `name [op version]` from a `DEPENDS=` list.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PkgDep {
+    pub name: String,
+    pub constraint: Option<(VersionOp, String)>,
+}
+
+impl PkgDep {
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let split = text.find(['<', '>', '=', ' ']).unwrap_or(text.len());
+        let name = text[..split].trim();
+        if !valid_name(name) {
+            return None;
+        }
+        let rest = text[split..].trim();
+        if rest.is_empty() {
+            return Some(Self {
+                name: name.to_ascii_lowercase(),
+                constraint: None,
+            });
+        }
+        let (op, version) = [
+            (">=", VersionOp::Ge),
+            ("<=", VersionOp::Le),
+            ("==", VersionOp::Eq),
+            ("=", VersionOp::Eq),
+            (">", VersionOp::Gt),
+            ("<", VersionOp::Lt),
+        ]
+        .iter()
+        .find_map(|(token, op)| rest.strip_prefix(token).map(|v| (*op, v.trim())))?;
+        if version.is_empty() || version.contains(char::is_whitespace) {
+            return None;
+        }
+        Some(Self {
+            name: name.to_ascii_lowercase(),
+            constraint: Some((op, String::from(version))),
+        })
+    }
+
+    pub fn matches(&self, version: &str) -> bool {
+        let Some((op, want)) = self.constraint.as_ref() else {
+            return true;
+        };
+        let ord = compare_versions(version, want.as_str());
+        match op {
+            VersionOp::Eq => ord == Ordering::Equal,
+            VersionOp::Ge => ord != Ordering::Less,
+            VersionOp::Gt => ord == Ordering::Greater,
+            VersionOp::Le => ord != Ordering::Greater,
+            VersionOp::Lt => ord == Ordering::Less,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self.constraint.as_ref() {
+            Some((op, v)) => alloc::format!("{} {} {}", self.name, op.as_str(), v),
+            None => self.name.clone(),
+        }
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || b == b'.')
+}
+
+/// Dotted versions: numeric parts compare as numbers, anything else as
+/// text, and a missing part sorts first (`1.2` < `1.2.1`).
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut left = a.split(['.', '-', '+']);
+    let mut right = b.split(['.', '-', '+']);
+    loop {
+        match (left.next(), right.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => {
+                let ord = match (x.parse::<u64>(), y.parse::<u64>()) {
+                    (Ok(x), Ok(y)) => x.cmp(&y),
+                    _ => x.cmp(y),
+                };
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+        }
+    }
+}
+
+/// One package version from the repository index.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct PkgRecord {
+    pub name: String,
+    pub version: String,
+    pub url: String,
+    pub size: usize,
+    pub sha256: String,
+    pub depends: Vec<PkgDep>,
+    pub summary: String,
+    /// Launcher entries as (label, command); `{DIR}` expands to the install
+    /// directory.
+    pub apps: Vec<(String, String)>,
+}
+
+impl PkgRecord {
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "version" => self.version = String::from(value),
+            "url" => self.url = String::from(value),
+            "size" => {
+                self.size = value
+                    .parse()
+                    .map_err(|_| alloc::format!("{}: SIZE invalido", self.name))?
+            }
+            "sha256" => self.sha256 = value.to_ascii_lowercase(),
+            "summary" => self.summary = String::from(value),
+            "depends" => {
+                for dep in value.split(',').filter(|d| !d.trim().is_empty()) {
+                    let parsed = PkgDep::parse(dep)
+                        .ok_or_else(|| alloc::format!("{}: dependencia invalida '{}'", self.name, dep.trim()))?;
+                    self.depends.push(parsed);
+                }
+            }
+            "app" => {
+                let (label, command) = value
+                    .split_once('|')
+                    .ok_or_else(|| alloc::format!("{}: APP debe ser etiqueta|comando", self.name))?;
+                if self.apps.len() < PKG_MAX_APPS && !label.trim().is_empty() && !command.trim().is_empty() {
+                    self.apps
+                        .push((String::from(label.trim()), String::from(command.trim())));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The index record rendered as a REDUX-SIG-V1 signature.
+    fn signature_text(&self) -> String {
+        alloc::format!(
+            "REDUX-SIG-V1\nALGO=sha256\nPACKAGE={}\nSIZE={}\nSHA256={}\n",
+            package_file_name(self.url.as_str()),
+            self.size,
+            self.sha256
+        )
+    }
+}
+
+fn package_file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// `key=value` lines grouped under `[name]` headers, after an optional
+/// header line.
+fn parse_sections(text: &str) -> Vec<(String, Vec<(String, String)>)> {
+    let mut out: Vec<(String, Vec<(String, String)>)> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            out.push((name.trim().to_ascii_lowercase(), Vec::new()));
+            continue;
+        }
+        let (Some((_, fields)), Some((key, value))) = (out.last_mut(), line.split_once('=')) else {
+            continue;
+        };
+        fields.push((key.trim().to_ascii_lowercase(), String::from(value.trim())));
+    }
+    out
+}
+
+/// Parse a repository index. Every record needs a version, URL, size and
+/// SHA-256.
+pub fn parse_index(text: &str) -> Result<Vec<PkgRecord>, String> {
+    let first = text.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+    if !first.eq_ignore_ascii_case(PKG_INDEX_HEADER) {
+        return Err(String::from("indice invalido (falta REDUX-PKG-INDEX-V1)."));
+    }
+    let mut records = Vec::new();
+    for (name, fields) in parse_sections(text) {
+        if !valid_name(name.as_str()) {
+            return Err(alloc::format!("indice: nombre de paquete invalido '{}'", name));
+        }
+        let mut record = PkgRecord {
+            name,
+            ..PkgRecord::default()
+        };
+        for (key, value) in fields.iter() {
+            record.set(key.as_str(), value.as_str())?;
+        }
+        let sha_ok = record.sha256.len() == 64 && record.sha256.bytes().all(|b| b.is_ascii_hexdigit());
+        if record.version.is_empty() || record.url.is_empty() || record.size == 0 || !sha_ok {
+            return Err(alloc::format!(
+                "indice: {} necesita VERSION, URL, SIZE y SHA256",
+                record.name
+            ));
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// An entry of the local package database.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct InstalledPkg {
+    pub name: String,
+    pub version: String,
+    /// Install directory relative to the volume root (`APPS/HELLO`).
+    pub dir: String,
+    /// Pulled in as a dependency rather than requested.
+    pub auto: bool,
+    pub depends: Vec<String>,
+    /// Root `.APP` launcher files written by the post-install hook.
+    pub apps: Vec<String>,
+    pub files: usize,
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+pub fn parse_db(text: &str) -> Vec<InstalledPkg> {
+    let mut out = Vec::new();
+    for (name, fields) in parse_sections(text) {
+        let mut pkg = InstalledPkg {
+            name,
+            ..InstalledPkg::default()
+        };
+        for (key, value) in fields.iter() {
+            match key.as_str() {
+                "version" => pkg.version = value.clone(),
+                "dir" => pkg.dir = value.clone(),
+                "auto" => pkg.auto = value == "1",
+                "depends" => pkg.depends = split_list(value),
+                "apps" => pkg.apps = split_list(value),
+                "files" => pkg.files = value.parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+        out.push(pkg);
+    }
+    out
+}
+
+pub fn serialize_db(db: &[InstalledPkg]) -> String {
+    let mut out = String::from(PKG_DB_HEADER);
+    out.push('\n');
+    for pkg in db.iter() {
+        out.push_str(
+            alloc::format!(
+                "[{}]\nVERSION={}\nDIR={}\nAUTO={}\nDEPENDS={}\nAPPS={}\nFILES={}\n",
+                pkg.name,
+                pkg.version,
+                pkg.dir,
+                if pkg.auto { 1 } else { 0 },
+                pkg.depends.join(","),
+                pkg.apps.join(","),
+                pkg.files
+            )
+            .as_str(),
+        );
+    }
+    out
+}
+
+/// One step of an install plan, in dependency order.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PlanItem {
+    /// Index into the repository records.
+    pub record: usize,
+    /// Requested by the user (not only needed by another package).
+    pub explicit: bool,
+    /// Installed version this step replaces.
+    pub replaces: Option<String>,
+}
+
+struct Resolver<'a> {
+    index: &'a [PkgRecord],
+    installed: &'a [InstalledPkg],
+    /// Packages that must come from the index even if installed (upgrades).
+    refresh: &'a [&'a str],
+    plan: Vec<PlanItem>,
+    visiting: Vec<String>,
+}
+
+impl Resolver<'_> {
+    fn best(&self, dep: &PkgDep) -> Option<usize> {
+        let mut best: Option<usize> = None;
+        for (i, record) in self.index.iter().enumerate() {
+            if record.name != dep.name || !dep.matches(record.version.as_str()) {
+                continue;
+            }
+            let newer = best.is_none_or(|b| {
+                compare_versions(record.version.as_str(), self.index[b].version.as_str()) == Ordering::Greater
+            });
+            if newer {
+                best = Some(i);
+            }
+        }
+        best
+    }
+
+    fn visit(&mut self, dep: &PkgDep, explicit: bool) -> Result<(), String> {
+        if let Some(item) = self.plan.iter_mut().find(|p| self.index[p.record].name == dep.name) {
+            item.explicit |= explicit;
+            return if dep.matches(self.index[item.record].version.as_str()) {
+                Ok(())
+            } else {
+                Err(alloc::format!(
+                    "conflicto: se requiere {} pero el plan ya usa {} {}",
+                    dep.describe(),
+                    dep.name,
+                    self.index[item.record].version
+                ))
+            };
+        }
+        if self.visiting.contains(&dep.name) {
+            return Err(alloc::format!(
+                "dependencia circular: {} -> {}",
+                self.visiting.join(" -> "),
+                dep.name
+            ));
+        }
+        let installed = self.installed.iter().find(|p| p.name == dep.name);
+        let refresh = self.refresh.contains(&dep.name.as_str());
+        if let Some(pkg) = installed {
+            if !explicit && !refresh && dep.matches(pkg.version.as_str()) {
+                return Ok(());
+            }
+        }
+        let record = self
+            .best(dep)
+            .ok_or_else(|| alloc::format!("{} no esta en el repositorio", dep.describe()))?;
+        if let Some(pkg) = installed {
+            if compare_versions(self.index[record].version.as_str(), pkg.version.as_str()) != Ordering::Greater
+                && dep.matches(pkg.version.as_str())
+            {
+                // Already at the best available version.
+                return Ok(());
+            }
+        }
+
+        self.visiting.push(dep.name.clone());
+        for sub in self.index[record].depends.iter() {
+            self.visit(sub, false)?;
+        }
+        self.visiting.pop();
+        self.plan.push(PlanItem {
+            record,
+            explicit,
+            replaces: installed.map(|p| p.version.clone()),
+        });
+        Ok(())
+    }
+}
+
+/// Packages to fetch, dependencies before dependents, so that `requested`
+/// and everything they need is installed at an acceptable version. Packages
+/// named in `refresh` are upgraded even when the installed version would do.
+pub fn resolve(
+    index: &[PkgRecord],
+    installed: &[InstalledPkg],
+    requested: &[PkgDep],
+    refresh: &[&str],
+) -> Result<Vec<PlanItem>, String> {
+    let mut resolver = Resolver {
+        index,
+        installed,
+        refresh,
+        plan: Vec::new(),
+        visiting: Vec::new(),
+    };
+    for dep in requested {
+        resolver.visit(dep, true)?;
+    }
+    Ok(resolver.plan)
+}
+
+fn sha256_hex(raw: &[u8]) -> String {
+    let digest = Sha256::digest(raw);
+    let mut out = String::with_capacity(64);
+    for byte in digest.iter() {
+        out.push_str(alloc::format!("{:02x}", byte).as_str());
+    }
+    out
+}
+
+/// Check `package_raw` against a REDUX-SIG-V1 signature: `ALGO=sha256`,
+/// optional `PACKAGE`/`SIZE`, the `SHA256` digest and an optional `SIG`
+/// that must repeat it. `precomputed_sha` skips hashing when the caller
+/// already has the digest.
+pub fn verify_signature(
+    package_name: &str,
+    package_raw: &[u8],
+    signature_text: &str,
+    precomputed_sha: Option<String>,
+) -> Result<(), String> {
+    let mut saw_header = false;
+    let mut algo: Option<String> = None;
+    let mut sig_package: Option<String> = None;
+    let mut sig_size: Option<usize> = None;
+    let mut sig_sha256: Option<String> = None;
+    let mut sig_field: Option<String> = None;
+
+    for line in signature_text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if !saw_header {
+            if !trimmed.eq_ignore_ascii_case("redux-sig-v1") {
+                return Err(String::from("firma invalida (.sig header)."));
+            }
+            saw_header = true;
+            continue;
+        }
+
+        let Some((key, value)) = trimmed.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "algo" => algo = Some(String::from(value)),
+            "package" => sig_package = Some(String::from(value)),
+            "size" if !value.is_empty() => {
+                sig_size = Some(value.parse().map_err(|_| String::from("firma invalida (.sig SIZE)."))?);
+            }
+            "sha256" => sig_sha256 = Some(String::from(value)),
+            "sig" => sig_field = Some(String::from(value)),
+            _ => {}
+        }
+    }
+
+    if !saw_header {
+        return Err(String::from("firma invalida (.sig vacia)."));
+    }
+
+    let algo_lower = algo.unwrap_or_default().trim().to_ascii_lowercase();
+    if algo_lower != "sha256" {
+        return Err(alloc::format!("firma invalida (ALGO={} no soportado).", algo_lower));
+    }
+
+    if let Some(pkg) = sig_package {
+        let pkg_trim = pkg.trim();
+        if !pkg_trim.is_empty() && !pkg_trim.eq_ignore_ascii_case(package_name) {
+            return Err(alloc::format!(
+                "firma invalida (PACKAGE={} != {}).",
+                pkg_trim,
+                package_name
+            ));
+        }
+    }
+
+    if let Some(size) = sig_size {
+        if size != package_raw.len() {
+            return Err(alloc::format!(
+                "firma invalida (SIZE={} != {}).",
+                size,
+                package_raw.len()
+            ));
+        }
+    }
+
+    let expected_sha = sig_sha256.unwrap_or_default().trim().to_ascii_lowercase();
+    let hex_ok = expected_sha
+        .bytes()
+        .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+    if expected_sha.len() != 64 || !hex_ok {
+        return Err(String::from("firma invalida (SHA256 no valido)."));
+    }
+
+    let computed_sha = precomputed_sha.unwrap_or_else(|| sha256_hex(package_raw));
+    if computed_sha != expected_sha {
+        return Err(alloc::format!(
+            "firma invalida (SHA256 mismatch esperado={} real={}).",
+            expected_sha,
+            computed_sha
+        ));
+    }
+
+    if let Some(sig) = sig_field {
+        let sig_text = sig.trim().to_ascii_lowercase();
+        if !sig_text.is_empty() && sig_text != expected_sha {
+            return Err(String::from("firma invalida (SIG mismatch)."));
+        }
+    }
+
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Storage
+// ---------------------------------------------------------------------------
+
+fn pkg_dir() -> Result<u32, String> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    fat.ensure_subdirectory(fat.root_cluster, PKG_DIR).map_err(String::from)
+}
+
+fn read_pkg_file(name: &str) -> Option<String> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let (_, dir) = fat
+        .resolve_path(fat.root_cluster, alloc::format!("{}/", PKG_DIR).as_str())
+        .ok()?;
+    let raw = fat.read_file_in_dir(dir, name).ok()?;
+    Some(String::from_utf8_lossy(raw.as_slice()).into_owned())
+}
+
+fn write_pkg_file(name: &str, text: &str) -> Result<(), String> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let dir = pkg_dir()?;
+    fat.write_text_file_in_dir(dir, name, text.as_bytes())
+        .map_err(String::from)
+}
+
+pub fn load_db() -> Vec<InstalledPkg> {
+    read_pkg_file(PKG_DB_FILE)
+        .map(|t| parse_db(t.as_str()))
+        .unwrap_or_default()
+}
+
+pub fn save_db(db: &[InstalledPkg]) -> Result<(), String> {
+    write_pkg_file(PKG_DB_FILE, serialize_db(db).as_str())
+}
+
+pub fn repo_url() -> Option<String> {
+    read_pkg_file(PKG_REPO_FILE)
+        .map(|t| String::from(t.trim()))
+        .filter(|u| !u.is_empty())
+}
+
+/// Cached index from the last `pkg update`.
+pub fn load_index() -> Result<Vec<PkgRecord>, String> {
+    let text = read_pkg_file(PKG_INDEX_FILE).ok_or_else(|| String::from("sin indice local (usa: pkg update)"))?;
+    parse_index(text.as_str())
+}
+
+// ---------------------------------------------------------------------------
+// Network
+// ---------------------------------------------------------------------------
+
+fn is_https(url: &str) -> bool {
+    url.get(..8).is_some_and(|s| s.eq_ignore_ascii_case("https://"))
+}
+
+/// Resolve a package URL relative to the index URL.
+fn join_url(base: &str, url: &str) -> String {
+    if url.contains("://") {
+        return String::from(url);
+    }
+    if url.starts_with('/') {
+        let origin_end = base.find("://").map(|i| i + 3).unwrap_or(0);
+        let host_end = base[origin_end..]
+            .find('/')
+            .map(|i| origin_end + i)
+            .unwrap_or(base.len());
+        return alloc::format!("{}{}", &base[..host_end], url);
+    }
+    let dir_end = base.rfind('/').map(|i| i + 1).unwrap_or(base.len());
+    alloc::format!("{}{}", &base[..dir_end], url)
+}
+
+/// GET over HTTPS, following a few redirects. Returns the body.
+fn https_get(url: &str, pump_ui: &mut impl FnMut()) -> Result<Vec<u8>, String> {
+    let mut url = String::from(url);
+    for _ in 0..=PKG_MAX_REDIRECTS {
+        if !is_https(url.as_str()) {
+            return Err(alloc::format!("{}: el repositorio debe usar https://", url));
+        }
+        let raw = crate::net::http_get_request_bytes(url.as_str(), pump_ui)
+            .ok_or_else(|| alloc::format!("{}: error de red", url))?;
+        let parsed = redux_netparse::http::parse_http_headers(raw.as_slice());
+        match parsed.status_code {
+            Some(200) => return Ok(raw.get(parsed.body_offset..).unwrap_or(&[]).to_vec()),
+            Some(301 | 302 | 303 | 307 | 308) => {
+                let location = parsed
+                    .headers
+                    .iter()
+                    .find(|(name, _)| name == "location")
+                    .map(|(_, value)| value.clone())
+                    .ok_or_else(|| alloc::format!("{}: redireccion sin Location", url))?;
+                url = join_url(url.as_str(), location.as_str());
+            }
+            Some(code) => return Err(alloc::format!("{}: HTTP {}", url, code)),
+            None => return Err(alloc::format!("{}: respuesta HTTP invalida", url)),
+        }
+    }
+    Err(String::from("demasiadas redirecciones"))
+}
+
+/// Download the index and cache it in `PKG/INDEX.TXT`.
+pub fn update_index(pump_ui: &mut impl FnMut()) -> Result<usize, String> {
+    let url = repo_url().ok_or_else(|| String::from("sin repositorio (usa: pkg repo <https://...>)"))?;
+    let body = https_get(url.as_str(), pump_ui)?;
+    let text = String::from_utf8_lossy(body.as_slice()).into_owned();
+    let records = parse_index(text.as_str())?;
+    write_pkg_file(PKG_INDEX_FILE, text.as_str())?;
+
+    let upgradable = upgradable(records.as_slice(), load_db().as_slice());
+    if !upgradable.is_empty() {
+        crate::gui::notifications::post(
+            "pkg",
+            "Actualizaciones disponibles",
+            alloc::format!("{} (pkg upgrade)", upgradable.join(", ")).as_str(),
+            crate::gui::notifications::Urgency::Info,
+        );
+    }
+    Ok(records.len())
+}
+
+/// Installed packages the index has a newer version of.
+pub fn upgradable(index: &[PkgRecord], db: &[InstalledPkg]) -> Vec<String> {
+    db.iter()
+        .filter(|pkg| {
+            index.iter().any(|r| {
+                r.name == pkg.name && compare_versions(r.version.as_str(), pkg.version.as_str()) == Ordering::Greater
+            })
+        })
+        .map(|pkg| pkg.name.clone())
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Install / remove
+// ---------------------------------------------------------------------------
+
+/// Short uppercase tag from a package name for 8.3 names.
+fn name_tag(name: &str, len: usize) -> String {
+    let tag: String = name
+        .bytes()
+        .filter(u8::is_ascii_alphanumeric)
+        .take(len)
+        .map(|b| b.to_ascii_uppercase() as char)
+        .collect();
+    if tag.is_empty() {
+        String::from("PKG")
+    } else {
+        tag
+    }
+}
+
+/// Install directory for a new package, avoiding directories other
+/// packages already use.
+fn install_dir_name(name: &str, db: &[InstalledPkg]) -> String {
+    let taken = |dir: &str| db.iter().any(|p| p.name != name && p.dir.eq_ignore_ascii_case(dir));
+    let dir = alloc::format!("{}/{}", PKG_APPS_DIR, name_tag(name, 8));
+    if !taken(dir.as_str()) {
+        return dir;
+    }
+    let crc = crate::compress::crc32(name.as_bytes());
+    alloc::format!("{}/{}{:02X}", PKG_APPS_DIR, name_tag(name, 6), crc & 0xFF)
+}
+
+/// Name used to delete `entry`: FAT32 deletes by 8.3 name, exFAT by the
+/// long one.
+fn fs_name(entry: &crate::fs::DirEntry) -> String {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get() };
+    if fat.mounted_fs == crate::fat32::DetectedFsKind::ExFat {
+        entry.full_name()
+    } else {
+        entry.short_name()
+    }
+}
+
+fn remove_tree(parent: u32, name: &str, cluster: u32) -> Result<(), String> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let entries = fat.read_dir_entries(cluster).map_err(String::from)?;
+    for entry in entries.iter() {
+        let child = fs_name(entry);
+        if !entry.valid || child == "." || child == ".." {
+            continue;
+        }
+        match entry.file_type {
+            FileType::File => fat.delete_file_in_dir(cluster, child.as_str()).map_err(String::from)?,
+            FileType::Directory => remove_tree(cluster, child.as_str(), entry.cluster)?,
+        }
+    }
+    fat.delete_directory_in_dir(parent, name).map_err(String::from)
+}
+
+/// Delete `dir` (relative to the root) and everything under it. A missing
+/// directory is not an error.
+fn remove_dir(dir: &str) -> Result<(), String> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let mut parent = fat.root_cluster;
+    let parts: Vec<&str> = dir.split('/').filter(|p| !p.is_empty()).collect();
+    for (i, part) in parts.iter().enumerate() {
+        let entries = fat.read_dir_entries(parent).map_err(String::from)?;
+        let Some(entry) = entries
+            .iter()
+            .find(|e| e.valid && e.file_type == FileType::Directory && e.matches_name(part))
+        else {
+            return Ok(());
+        };
+        if i + 1 == parts.len() {
+            return remove_tree(parent, fs_name(entry).as_str(), entry.cluster);
+        }
+        parent = entry.cluster;
+    }
+    Ok(())
+}
+
+fn remove_launchers(apps: &[String]) {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    for app in apps.iter() {
+        let _ = fat.delete_file_in_dir(fat.root_cluster, app.as_str());
+    }
+}
+
+/// Post-install hook: one root `NAMEnn.APP` per launcher entry.
+fn register_launchers(pkg: &str, dir: &str, apps: &[(String, String)]) -> Result<Vec<String>, String> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let tag = name_tag(pkg, 6);
+    let dir_path = alloc::format!("/{}", dir);
+    let mut written = Vec::new();
+    for (i, (label, command)) in apps.iter().enumerate() {
+        let file = alloc::format!("{}{:02}.APP", tag, i + 1);
+        let text = alloc::format!(
+            "NAME={}\nCMD={}\nSOURCE=pkg:{}\n",
+            label,
+            command.replace("{DIR}", dir_path.as_str()),
+            pkg
+        );
+        fat.write_text_file_in_dir(fat.root_cluster, file.as_str(), text.as_bytes())
+            .map_err(String::from)?;
+        written.push(file);
+    }
+    Ok(written)
+}
+
+/// Download, verify and unpack one plan step, replacing an older version
+/// if present. Updates `db` in memory.
+fn install_one(
+    record: &PkgRecord,
+    explicit: bool,
+    index_url: &str,
+    db: &mut Vec<InstalledPkg>,
+    pump_ui: &mut impl FnMut(),
+) -> Result<String, String> {
+    let _t = crate::trace::scope_with("installer", "pkg_install", record.name.as_str());
+    let url = join_url(index_url, record.url.as_str());
+    let raw = https_get(url.as_str(), pump_ui)?;
+    verify_signature(
+        package_file_name(record.url.as_str()),
+        raw.as_slice(),
+        record.signature_text().as_str(),
+        None,
+    )?;
+    let archive = Archive::open(raw).map_err(String::from)?;
+
+    // Launchers from the index plus any the package declares itself.
+    let mut apps = record.apps.clone();
+    if let Some(info) = archive.find(PKG_INFO_MEMBER) {
+        let text = archive.read(info).map_err(String::from)?;
+        let text = String::from_utf8_lossy(text.as_slice()).into_owned();
+        for line in text.lines() {
+            let Some(value) = line.trim().strip_prefix("APP=") else {
+                continue;
+            };
+            if let Some((label, command)) = value.split_once('|') {
+                if apps.len() < PKG_MAX_APPS && !apps.iter().any(|(l, _)| l == label.trim()) {
+                    apps.push((String::from(label.trim()), String::from(command.trim())));
+                }
+            }
+        }
+    }
+
+    let previous = db.iter().position(|p| p.name == record.name).map(|i| db.remove(i));
+    let auto = previous.as_ref().map(|p| p.auto && !explicit).unwrap_or(!explicit);
+    let dir = match previous.as_ref() {
+        Some(old) => {
+            remove_launchers(old.apps.as_slice());
+            remove_dir(old.dir.as_str())?;
+            old.dir.clone()
+        }
+        None => install_dir_name(record.name.as_str(), db.as_slice()),
+    };
+
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let mut target = fat.root_cluster;
+    for part in dir.split('/') {
+        target = fat.ensure_subdirectory(target, part).map_err(String::from)?;
+    }
+    let report = crate::archive::extract_to_dir(&archive, target, &mut |_| pump_ui());
+    if let Some(first) = report.errors.first() {
+        return Err(alloc::format!("{} errores al extraer ({})", report.errors.len(), first));
+    }
+    let launchers = register_launchers(record.name.as_str(), dir.as_str(), apps.as_slice())?;
+
+    db.push(InstalledPkg {
+        name: record.name.clone(),
+        version: record.version.clone(),
+        dir: dir.clone(),
+        auto,
+        depends: record.depends.iter().map(|d| d.name.clone()).collect(),
+        apps: launchers.clone(),
+        files: report.files,
+    });
+    Ok(alloc::format!(
+        "{} {} -> /{} ({} archivos{}{})",
+        record.name,
+        record.version,
+        dir,
+        report.files,
+        if launchers.is_empty() { "" } else { ", lanzadores: " },
+        launchers.join(" ")
+    ))
+}
+
+fn run_plan(plan: &[PlanItem], index: &[PkgRecord], pump_ui: &mut impl FnMut()) -> Vec<String> {
+    let mut out = Vec::new();
+    if plan.is_empty() {
+        out.push(String::from("pkg: nada que hacer."));
+        return out;
+    }
+    let index_url = repo_url().unwrap_or_default();
+    let mut db = load_db();
+    for item in plan.iter() {
+        let record = &index[item.record];
+        let verb = match item.replaces.as_ref() {
+            Some(old) => alloc::format!("Actualizando {} {} -> {}", record.name, old, record.version),
+            None => alloc::format!("Instalando {} {}", record.name, record.version),
+        };
+        out.push(verb);
+        match install_one(record, item.explicit, index_url.as_str(), &mut db, pump_ui) {
+            Ok(line) => out.push(alloc::format!("  {}", line)),
+            Err(e) => {
+                out.push(alloc::format!("  error: {}", e));
+                out.push(String::from(
+                    "pkg: operacion detenida; los paquetes anteriores quedaron instalados.",
+                ));
+                break;
+            }
+        }
+    }
+    if let Err(e) = save_db(db.as_slice()) {
+        out.push(alloc::format!("pkg: no se pudo guardar la base de datos: {}", e));
+    }
+    out
+}
+
+/// Packages that list `name` among their dependencies.
+fn dependents<'a>(db: &'a [InstalledPkg], name: &str) -> Vec<&'a str> {
+    db.iter()
+        .filter(|p| p.depends.iter().any(|d| d == name))
+        .map(|p| p.name.as_str())
+        .collect()
+}
+
+pub fn remove(name: &str) -> Result<String, String> {
+    let mut db = load_db();
+    let pos = db
+        .iter()
+        .position(|p| p.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| alloc::format!("{} no esta instalado", name))?;
+    let users = dependents(db.as_slice(), db[pos].name.as_str());
+    if !users.is_empty() {
+        return Err(alloc::format!(
+            "{} es necesario para: {}",
+            db[pos].name,
+            users.join(", ")
+        ));
+    }
+    let pkg = db.remove(pos);
+    remove_launchers(pkg.apps.as_slice());
+    remove_dir(pkg.dir.as_str())?;
+    save_db(db.as_slice())?;
+    Ok(alloc::format!("{} {} eliminado (/{}).", pkg.name, pkg.version, pkg.dir))
+}
+
+/// Auto-installed packages nothing depends on any more.
+fn orphans(db: &[InstalledPkg]) -> Vec<String> {
+    db.iter()
+        .filter(|p| p.auto && dependents(db, p.name.as_str()).is_empty())
+        .map(|p| p.name.clone())
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Command
+// ---------------------------------------------------------------------------
+
+fn list_or_dash(items: &[String], sep: &str) -> String {
+    if items.is_empty() {
+        String::from("-")
+    } else {
+        items.join(sep)
+    }
+}
+
+fn auto_note(pkg: &InstalledPkg) -> &'static str {
+    if pkg.auto {
+        " (dependencia)"
+    } else {
+        ""
+    }
+}
+
+fn record_line(record: &PkgRecord, db: &[InstalledPkg]) -> String {
+    let state = match db.iter().find(|p| p.name == record.name) {
+        Some(p) if p.version == record.version => String::from(" [instalado]"),
+        Some(p) => alloc::format!(" [instalado {}]", p.version),
+        None => String::new(),
+    };
+    alloc::format!("  {} {}{} - {}", record.name, record.version, state, record.summary)
+}
+
+/// Shared implementation of `pkg`.
+pub fn command_lines(args: &str, pump_ui: &mut impl FnMut()) -> Vec<String> {
+    let mut words = args.split_whitespace();
+    let sub = words.next().unwrap_or("status").to_ascii_lowercase();
+    let rest: Vec<&str> = words.collect();
+    let mut out = Vec::new();
+
+    match sub.as_str() {
+        "status" => {
+            let db = load_db();
+            out.push(alloc::format!(
+                "pkg: repositorio {}",
+                repo_url().unwrap_or_else(|| String::from("(ninguno)"))
+            ));
+            out.push(alloc::format!(
+                "pkg: {} instalados, indice local: {}",
+                db.len(),
+                match load_index() {
+                    Ok(index) => alloc::format!("{} paquetes", index.len()),
+                    Err(e) => e,
+                }
+            ));
+        }
+        "repo" => match rest.first() {
+            Some(url) if is_https(url) => match write_pkg_file(PKG_REPO_FILE, url) {
+                Ok(()) => out.push(alloc::format!("pkg: repositorio {} (ejecuta pkg update)", url)),
+                Err(e) => out.push(alloc::format!("pkg: {}", e)),
+            },
+            Some(_) => out.push(String::from("pkg: el repositorio debe usar https://")),
+            None => out.push(alloc::format!(
+                "pkg: repositorio {}",
+                repo_url().unwrap_or_else(|| String::from("(ninguno)"))
+            )),
+        },
+        "update" => match update_index(pump_ui) {
+            Ok(count) => out.push(alloc::format!("pkg: indice actualizado, {} paquetes.", count)),
+            Err(e) => out.push(alloc::format!("pkg: {}", e)),
+        },
+        "search" => match load_index() {
+            Ok(index) => {
+                let db = load_db();
+                let needle = rest.join(" ").to_ascii_lowercase();
+                for record in index.iter() {
+                    let hay = alloc::format!("{} {}", record.name, record.summary).to_ascii_lowercase();
+                    if needle.is_empty() || hay.contains(needle.as_str()) {
+                        out.push(record_line(record, db.as_slice()));
+                    }
+                }
+                if out.is_empty() {
+                    out.push(String::from("pkg: sin resultados."));
+                }
+            }
+            Err(e) => out.push(alloc::format!("pkg: {}", e)),
+        },
+        "info" => {
+            let Some(name) = rest.first() else {
+                out.push(String::from("Uso: pkg info <paquete>"));
+                return out;
+            };
+            let name = name.to_ascii_lowercase();
+            let db = load_db();
+            if let Some(pkg) = db.iter().find(|p| p.name == name) {
+                out.push(alloc::format!("{} {} instalado en /{}{}", pkg.name, pkg.version, pkg.dir, auto_note(pkg)));
+                out.push(alloc::format!("  depende de: {}", list_or_dash(pkg.depends.as_slice(), ", ")));
+                out.push(alloc::format!("  lanzadores: {}", list_or_dash(pkg.apps.as_slice(), " ")));
+            }
+            if let Ok(index) = load_index() {
+                for record in index.iter().filter(|r| r.name == name) {
+                    out.push(alloc::format!(
+                        "{} {} en repositorio ({} bytes): {}",
+                        record.name, record.version, record.size, record.summary
+                    ));
+                    let deps: Vec<String> = record.depends.iter().map(PkgDep::describe).collect();
+                    out.push(alloc::format!("  requiere: {}", list_or_dash(deps.as_slice(), ", ")));
+                }
+            }
+            if out.is_empty() {
+                out.push(alloc::format!("pkg: {} desconocido.", name));
+            }
+        }
+        "list" => {
+            let db = load_db();
+            for pkg in db.iter() {
+                out.push(alloc::format!("  {} {}{}", pkg.name, pkg.version, auto_note(pkg)));
+            }
+            out.push(alloc::format!("{} paquetes instalados.", db.len()));
+        }
+        "install" | "upgrade" => {
+            let index = match load_index() {
+                Ok(index) => index,
+                Err(e) => {
+                    out.push(alloc::format!("pkg: {}", e));
+                    return out;
+                }
+            };
+            let db = load_db();
+            let (requested, refresh): (Vec<PkgDep>, Vec<&str>) = if sub == "install" {
+                if rest.is_empty() {
+                    out.push(String::from("Uso: pkg install <paquete>[=version]..."));
+                    return out;
+                }
+                let mut requested = Vec::new();
+                for spec in rest.iter() {
+                    match PkgDep::parse(spec) {
+                        Some(dep) => requested.push(dep),
+                        None => {
+                            out.push(alloc::format!("pkg: especificacion invalida '{}'", spec));
+                            return out;
+                        }
+                    }
+                }
+                (requested, Vec::new())
+            } else {
+                let targets: Vec<&InstalledPkg> = db
+                    .iter()
+                    .filter(|p| rest.is_empty() || rest.iter().any(|n| p.name.eq_ignore_ascii_case(n)))
+                    .collect();
+                let requested = targets
+                    .iter()
+                    .map(|p| PkgDep {
+                        name: p.name.clone(),
+                        constraint: Some((VersionOp::Ge, p.version.clone())),
+                    })
+                    .collect();
+                (requested, targets.iter().map(|p| p.name.as_str()).collect())
+            };
+            match resolve(index.as_slice(), db.as_slice(), requested.as_slice(), refresh.as_slice()) {
+                Ok(mut plan) => {
+                    if sub == "upgrade" {
+                        // Upgrading does not change why a package was installed.
+                        for item in plan.iter_mut() {
+                            let name = index[item.record].name.as_str();
+                            item.explicit = db.iter().any(|p| p.name == name && !p.auto);
+                        }
+                    }
+                    out.extend(run_plan(plan.as_slice(), index.as_slice(), pump_ui));
+                }
+                Err(e) => out.push(alloc::format!("pkg: {}", e)),
+            }
+        }
+        "remove" => {
+            let Some(name) = rest.first() else {
+                out.push(String::from("Uso: pkg remove <paquete>"));
+                return out;
+            };
+            match remove(name) {
+                Ok(line) => out.push(line),
+                Err(e) => out.push(alloc::format!("pkg: {}", e)),
+            }
+            let left = orphans(load_db().as_slice());
+            if !left.is_empty() {
+                out.push(alloc::format!("pkg: ya no se necesitan: {} (pkg autoremove)", left.join(", ")));
+            }
+        }
+        "autoremove" => loop {
+            let left = orphans(load_db().as_slice());
+            let Some(name) = left.first() else {
+                if out.is_empty() {
+                    out.push(String::from("pkg: nada que eliminar."));
+                }
+                break;
+            };
+            match remove(name.as_str()) {
+                Ok(line) => out.push(line),
+                Err(e) => {
+                    out.push(alloc::format!("pkg: {}", e));
+                    break;
+                }
+            }
+        },
+        _ => out.push(String::from(
+            "Uso: pkg [status|repo <https-url>|update|search [texto]|info <paquete>|list|install <paquete>[>=ver]...|remove <paquete>|autoremove|upgrade [paquete...]]",
+        )),
+    }
+    out
+}
+
+/// Test index text with a dummy digest wherever `HASH` appears.
+#[cfg(feature = "selftest")]
+fn test_index(sections: &str) -> Result<Vec<PkgRecord>, String> {
+    let zero = "0000000000000000000000000000000000000000000000000000000000000000";
+    parse_index(alloc::format!("REDUX-PKG-INDEX-V1\n{}", sections.replace("HASH", zero)).as_str())
+}
+
+crate::selftest::kernel_tests! {
+    "pkg";
+
+    fn version_ordering() {
+        crate::selftest::ensure_eq(compare_versions("1.10.0", "1.9.3"), Ordering::Greater, "numerico")?;
+        crate::selftest::ensure_eq(compare_versions("1.2", "1.2.1"), Ordering::Less, "prefijo")?;
+        crate::selftest::ensure_eq(compare_versions("2.0-rc1", "2.0-rc1"), Ordering::Equal, "igual")?;
+        let dep = PkgDep::parse("libui >= 0.3").ok_or("parse")?;
+        crate::selftest::ensure(dep.matches("0.3") && dep.matches("1.0") && !dep.matches("0.2.9"), ">=")?;
+        crate::selftest::ensure(PkgDep::parse("a<1").is_some_and(|d| !d.matches("1.0")), "<")?;
+        crate::selftest::ensure(PkgDep::parse("bad name!").is_none(), "nombre invalido")
+    }
+
+    fn resolve_orders_dependencies() {
+        let index = test_index(concat!(
+            "[app]\nVERSION=1.0\nURL=app.tgz\nSIZE=1\nSHA256=HASH\nDEPENDS=lib >= 2, fonts\n",
+            "[lib]\nVERSION=1.5\nURL=lib-1.5.tgz\nSIZE=1\nSHA256=HASH\n",
+            "[lib]\nVERSION=2.1\nURL=lib-2.1.tgz\nSIZE=1\nSHA256=HASH\nDEPENDS=fonts\n",
+            "[fonts]\nVERSION=3\nURL=fonts.zip\nSIZE=1\nSHA256=HASH\n",
+        ))?;
+        let want = [PkgDep::parse("app").ok_or("parse")?];
+        let plan = resolve(index.as_slice(), &[], &want, &[])?;
+        let names: Vec<&str> = plan.iter().map(|p| index[p.record].name.as_str()).collect();
+        crate::selftest::ensure_eq(names, alloc::vec!["fonts", "lib", "app"], "orden")?;
+        crate::selftest::ensure_eq(index[plan[1].record].version.as_str(), "2.1", "version mas nueva")?;
+        crate::selftest::ensure(plan[2].explicit && !plan[0].explicit, "explicito")?;
+
+        let installed = [InstalledPkg {
+            name: String::from("fonts"),
+            version: String::from("3"),
+            ..InstalledPkg::default()
+        }];
+        let plan = resolve(index.as_slice(), &installed, &want, &[])?;
+        crate::selftest::ensure_eq(plan.len(), 2, "instalado satisface")?;
+
+        let old = [PkgDep::parse("lib=1.5").ok_or("parse")?, PkgDep::parse("app").ok_or("parse")?];
+        crate::selftest::ensure(resolve(index.as_slice(), &[], &old, &[]).is_err(), "conflicto")?;
+        let missing = [PkgDep::parse("nada").ok_or("parse")?];
+        crate::selftest::ensure(resolve(index.as_slice(), &[], &missing, &[]).is_err(), "faltante")
+    }
+
+    fn resolve_detects_cycles() {
+        let index = test_index(concat!(
+            "[a]\nVERSION=1\nURL=a\nSIZE=1\nSHA256=HASH\nDEPENDS=b\n",
+            "[b]\nVERSION=1\nURL=b\nSIZE=1\nSHA256=HASH\nDEPENDS=a\n",
+        ))?;
+        let want = [PkgDep::parse("a").ok_or("parse")?];
+        crate::selftest::ensure(resolve(index.as_slice(), &[], &want, &[]).is_err(), "ciclo")
+    }
+
+    fn db_roundtrip() {
+        let db = alloc::vec![InstalledPkg {
+            name: String::from("hello"),
+            version: String::from("1.2.0"),
+            dir: String::from("APPS/HELLO"),
+            auto: true,
+            depends: alloc::vec![String::from("libui")],
+            apps: alloc::vec![String::from("HELLO01.APP")],
+            files: 3,
+        }];
+        crate::selftest::ensure(parse_db(serialize_db(db.as_slice()).as_str()) == db, "db")
+    }
+
+    fn signature_checks_digest() {
+        let data = b"paquete";
+        let sig = alloc::format!("REDUX-SIG-V1\nALGO=sha256\nPACKAGE=p.tgz\nSIZE=7\nSHA256={}\n", sha256_hex(data));
+        verify_signature("p.tgz", data, sig.as_str(), None)?;
+        crate::selftest::ensure(verify_signature("p.tgz", b"paquetE", sig.as_str(), None).is_err(), "sha")?;
+        crate::selftest::ensure(verify_signature("q.tgz", data, sig.as_str(), None).is_err(), "nombre")?;
+        let base = "https://r.example/pub/index.txt";
+        crate::selftest::ensure_eq(join_url(base, "a.tgz").as_str(), "https://r.example/pub/a.tgz", "url relativa")?;
+        crate::selftest::ensure_eq(join_url(base, "/x/a.tgz").as_str(), "https://r.example/x/a.tgz", "url absoluta")
+    }
+}
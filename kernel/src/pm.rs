@@ -0,0 +1,324 @@
+//! Power management: driver suspend/resume hooks, sleep entry and the
+//! lid switch / power buttons.
+//!
+//! Drivers register a `PmOps` from their probe. `suspend_devices` quiesces
+//! them in reverse registration order, so a device goes down before
+//! anything it was probed after, and `resume_devices` brings them back in
+//! probe order. `sleep` then either enters ACPI S3, where the firmware cuts
+//! power to the devices and the hooks re-program them on wake, or leaves
+//! the machine in s2idle: devices quiesced, the desktop blanked and the
+//! main loop idling until a key, the lid or the power button wakes it.
+//!
+//! `power.sleep_mode` picks `auto` (default: S3, s2idle when the firmware
+//! refuses), `s3` or `s2idle`; `power.lid_action` is `suspend` (default)
+//! or `ignore`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+
+use crate::config::ConfigValue;
+use crate::spinlock::SpinLock;
+
+pub const SLEEP_MODE_KEY: &str = "power.sleep_mode";
+pub const LID_ACTION_KEY: &str = "power.lid_action";
+const EVENT_POLL_INTERVAL_MS: u64 = 250;
+
+/// A driver's power hooks. `suspend` may refuse, which aborts the sleep
+/// and resumes whatever was already suspended.
+pub struct PmOps {
+    pub name: &'static str,
+    pub suspend: fn() -> Result<(), &'static str>,
+    pub resume: fn(),
+}
+
+static OPS: SpinLock<Vec<&'static PmOps>> = SpinLock::new(Vec::new());
+static SUSPENDED: AtomicBool = AtomicBool::new(false);
+
+/// Adds a driver's hooks; a second registration under the same name (a
+/// re-probe) replaces the first without changing its place in the order.
+pub fn register(ops: &'static PmOps) {
+    let mut list = OPS.lock();
+    match list.iter_mut().find(|existing| existing.name == ops.name) {
+        Some(existing) => *existing = ops,
+        None => list.push(ops),
+    }
+}
+
+/// Drops a driver's hooks, once `driver_guard` has disabled it.
+pub fn unregister(name: &str) {
+    OPS.lock().retain(|ops| ops.name != name);
+}
+
+pub fn registered() -> Vec<&'static str> {
+    OPS.lock().iter().map(|ops| ops.name).collect()
+}
+
+pub fn devices_suspended() -> bool {
+    SUSPENDED.load(Ordering::SeqCst)
+}
+
+/// Suspends `ops` last to first. On a refusal the ones already suspended
+/// are resumed again and the refusing driver is reported. A driver whose
+/// hook faults is disabled by `driver_guard` and does not hold up the rest.
+fn suspend_all(ops: &[&'static PmOps]) -> Result<(), String> {
+    for (index, driver) in ops.iter().enumerate().rev() {
+        if let Some(Err(err)) = crate::driver_guard::call(driver.name, driver.suspend) {
+            for resumed in &ops[index + 1..] {
+                crate::driver_guard::call(resumed.name, resumed.resume);
+            }
+            return Err(alloc::format!("{}: {}", driver.name, err));
+        }
+    }
+    Ok(())
+}
+
+pub fn suspend_devices() -> Result<(), String> {
+    if SUSPENDED.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+    // Copied out so a hook may log or touch config without the lock held.
+    let ops = OPS.lock().clone();
+    suspend_all(&ops)?;
+    SUSPENDED.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+pub fn resume_devices() {
+    if !SUSPENDED.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    let ops = OPS.lock().clone();
+    for driver in ops.iter() {
+        crate::driver_guard::call(driver.name, driver.resume);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SleepMode {
+    Auto,
+    S3,
+    S2Idle,
+}
+
+impl SleepMode {
+    pub fn parse(value: &str) -> Option<SleepMode> {
+        match value.trim() {
+            "auto" => Some(SleepMode::Auto),
+            "s3" | "mem" => Some(SleepMode::S3),
+            "s2idle" | "idle" | "freeze" => Some(SleepMode::S2Idle),
+            _ => None,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            SleepMode::Auto => "auto",
+            SleepMode::S3 => "s3",
+            SleepMode::S2Idle => "s2idle",
+        }
+    }
+}
+
+pub fn sleep_mode() -> SleepMode {
+    SleepMode::parse(crate::config::get_str(SLEEP_MODE_KEY, "auto").as_str()).unwrap_or(SleepMode::Auto)
+}
+
+pub fn lid_suspends() -> bool {
+    crate::config::get_str(LID_ACTION_KEY, "suspend").trim() != "ignore"
+}
+
+/// How a sleep ended.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Slept {
+    /// Came back from S3; devices are already resumed, the caller restores
+    /// the display mode.
+    Resumed,
+    /// s2idle: devices stay quiesced until the caller sees a wake event
+    /// and calls `resume_devices`.
+    Idle,
+}
+
+pub fn sleep(mode: SleepMode) -> Result<Slept, String> {
+    suspend_devices()?;
+    if mode == SleepMode::S2Idle {
+        return Ok(Slept::Idle);
+    }
+    match crate::acpi::try_suspend_to_ram() {
+        Ok(()) => {
+            resume_devices();
+            // Whatever woke the machine is still latched; it is not a new press.
+            let _ = crate::acpi::take_button_events();
+            LID.store(LID_UNKNOWN, Ordering::SeqCst);
+            Ok(Slept::Resumed)
+        }
+        Err(err) if mode == SleepMode::Auto => {
+            crate::println(alloc::format!("PM: S3 no disponible ({}), usando s2idle.", err).as_str());
+            Ok(Slept::Idle)
+        }
+        Err(err) => {
+            resume_devices();
+            Err(String::from(err))
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PowerEvent {
+    LidClosed,
+    LidOpened,
+    PowerButton,
+    SleepButton,
+}
+
+const LID_UNKNOWN: u8 = 0;
+const LID_OPEN: u8 = 1;
+const LID_CLOSED: u8 = 2;
+
+static LID: AtomicU8 = AtomicU8::new(LID_UNKNOWN);
+static NEXT_POLL_MS: AtomicU64 = AtomicU64::new(0);
+static PENDING_SLEEP_BUTTON: AtomicBool = AtomicBool::new(false);
+
+/// The event for a lid reading given the last one; the first reading only
+/// sets the baseline.
+fn lid_transition(last: u8, open: bool) -> Option<PowerEvent> {
+    match (last, open) {
+        (LID_OPEN, false) => Some(PowerEvent::LidClosed),
+        (LID_CLOSED, true) => Some(PowerEvent::LidOpened),
+        _ => None,
+    }
+}
+
+/// The next power event, if any. Buttons are read from the ACPI fixed
+/// events and the lid from the field behind `_LID`, at most every
+/// `EVENT_POLL_INTERVAL_MS`.
+pub fn poll_events() -> Option<PowerEvent> {
+    if PENDING_SLEEP_BUTTON.swap(false, Ordering::SeqCst) {
+        return Some(PowerEvent::SleepButton);
+    }
+    let now = crate::timer::now_ms();
+    if now < NEXT_POLL_MS.load(Ordering::Relaxed) {
+        return None;
+    }
+    NEXT_POLL_MS.store(now + EVENT_POLL_INTERVAL_MS, Ordering::Relaxed);
+
+    let (power, sleep) = crate::acpi::take_button_events();
+    if power {
+        PENDING_SLEEP_BUTTON.store(sleep, Ordering::SeqCst);
+        return Some(PowerEvent::PowerButton);
+    }
+    if sleep {
+        return Some(PowerEvent::SleepButton);
+    }
+    let open = crate::acpi::lid_open()?;
+    let last = LID.swap(if open { LID_OPEN } else { LID_CLOSED }, Ordering::SeqCst);
+    lid_transition(last, open)
+}
+
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim();
+    let (sub, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    match sub {
+        "" | "status" => {
+            let mut out = alloc::vec![
+                alloc::format!("Modo de suspension: {}", sleep_mode().name()),
+                alloc::format!(
+                    "Tapa: {}",
+                    if lid_suspends() {
+                        "suspender al cerrar"
+                    } else {
+                        "ignorar"
+                    }
+                ),
+                crate::acpi::lid_status_line(),
+                crate::acpi::s3_status_line(),
+            ];
+            let names = registered();
+            out.push(alloc::format!(
+                "Drivers con suspend/resume: {}{}",
+                if names.is_empty() {
+                    String::from("ninguno")
+                } else {
+                    names.join(", ")
+                },
+                if devices_suspended() { " (suspendidos)" } else { "" }
+            ));
+            out
+        }
+        "mode" => {
+            let Some(mode) = SleepMode::parse(rest) else {
+                return alloc::vec![String::from("Uso: power mode auto|s3|s2idle")];
+            };
+            match crate::config::set(SLEEP_MODE_KEY, ConfigValue::Str(String::from(mode.name()))) {
+                Ok(()) => alloc::vec![alloc::format!("Modo de suspension: {}", mode.name())],
+                Err(e) => alloc::vec![alloc::format!("config: {}", e)],
+            }
+        }
+        "lid" => {
+            let action = rest.trim();
+            if action != "suspend" && action != "ignore" {
+                return alloc::vec![String::from("Uso: power lid suspend|ignore")];
+            }
+            match crate::config::set(LID_ACTION_KEY, ConfigValue::Str(String::from(action))) {
+                Ok(()) => alloc::vec![alloc::format!("Tapa: {}", action)],
+                Err(e) => alloc::vec![alloc::format!("config: {}", e)],
+            }
+        }
+        "acpi" => alloc::vec![crate::acpi::s3_status_line()],
+        _ => alloc::vec![String::from(
+            "Uso: power [status|mode auto|s3|s2idle|lid suspend|ignore|acpi]"
+        )],
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "pm";
+
+    fn suspend_order_and_rollback() {
+        static LOG: SpinLock<Vec<&'static str>> = SpinLock::new(Vec::new());
+        static NIC: PmOps = PmOps {
+            name: "nic",
+            suspend: || {
+                LOG.lock().push("nic-");
+                Err("ocupado")
+            },
+            resume: || LOG.lock().push("nic+"),
+        };
+        static DISK: PmOps = PmOps {
+            name: "disk",
+            suspend: || {
+                LOG.lock().push("disk-");
+                Ok(())
+            },
+            resume: || LOG.lock().push("disk+"),
+        };
+        static DISPLAY: PmOps = PmOps {
+            name: "display",
+            suspend: || {
+                LOG.lock().push("display-");
+                Ok(())
+            },
+            resume: || LOG.lock().push("display+"),
+        };
+        let result = suspend_all(&[&NIC, &DISK, &DISPLAY]);
+        crate::selftest::ensure_eq(result, Err(String::from("nic: ocupado")), "el nic se niega")?;
+        crate::selftest::ensure_eq(
+            LOG.lock().clone(),
+            alloc::vec!["display-", "disk-", "nic-", "disk+", "display+"],
+            "orden inverso y vuelta atras",
+        )
+    }
+
+    fn lid_edges_only() {
+        crate::selftest::ensure_eq(lid_transition(LID_UNKNOWN, false), None, "primera lectura")?;
+        crate::selftest::ensure_eq(lid_transition(LID_OPEN, false), Some(PowerEvent::LidClosed), "cerrar")?;
+        crate::selftest::ensure_eq(lid_transition(LID_CLOSED, true), Some(PowerEvent::LidOpened), "abrir")?;
+        crate::selftest::ensure_eq(lid_transition(LID_CLOSED, false), None, "sigue cerrada")
+    }
+
+    fn sleep_mode_names() {
+        crate::selftest::ensure_eq(SleepMode::parse(" s2idle "), Some(SleepMode::S2Idle), "s2idle")?;
+        crate::selftest::ensure_eq(SleepMode::parse("mem"), Some(SleepMode::S3), "alias mem")?;
+        crate::selftest::ensure_eq(SleepMode::parse("s4"), None, "no soportado")
+    }
+}
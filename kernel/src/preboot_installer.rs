@@ -5,6 +5,7 @@ use alloc::vec::Vec;
 
 use crate::framebuffer::{self, rgb, FramebufferInfo, PixelLayout};
 use crate::fs::FileSystem;
+use crate::i18n::{tr, trf};
 use crate::input::{self, RuntimeInput, RuntimeKey};
 
 use uefi::boot::{self, OpenProtocolAttributes, OpenProtocolParams};
@@ -242,6 +243,13 @@ pub fn run() -> InstallerResult {
     crate::println("Preboot installer: optional GRUB payload");
     crate::println_num(if grub_enabled { 1 } else { 0 });
 
+    draw_bootstrap_progress("LOADING BOOT MANAGER", 80, "CHECKING SECURE BOOT");
+    let secure_boot = crate::secureboot::state();
+    let shim_assets = crate::secureboot::load_assets();
+    crate::println("Preboot installer: secure boot / shim assets");
+    crate::println_num(secure_boot.enabled as u64);
+    crate::println_num(shim_assets.is_some() as u64);
+
     draw_bootstrap_progress("SCANNING DISKS", 84, "DISCOVERING INTERNAL TARGETS");
     crate::println("Preboot installer: discover disks");
     let mut disks = discover_internal_disks();
@@ -264,8 +272,16 @@ pub fn run() -> InstallerResult {
         String::from("NO INTERNAL DISKS DETECTED. PRESS ESC TO SKIP.")
     } else if targets.is_empty() {
         String::from("NO PARTITIONS. PRESS C TO CREATE 16 GIB FAT32 BOOT + REST EXFAT DATA.")
+    } else if secure_boot.enabled && shim_assets.is_none() {
+        tr("installer.sb_no_shim")
     } else {
-        let boot_manager = if grub_enabled { "GRUB" } else { "REDUXEFI" };
+        let boot_manager = if shim_assets.is_some() {
+            "SHIM"
+        } else if grub_enabled {
+            "GRUB"
+        } else {
+            "REDUXEFI"
+        };
         format!(
             "READY. PAYLOAD {} KB. LINUXRT {} FILES. SERVORT {} FILES. BOOTMGR {}. SELECT TARGET + ENTER.",
             payload.len() / 1024,
@@ -507,7 +523,7 @@ pub fn run() -> InstallerResult {
                     }
 
                     if targets.is_empty() {
-                        status = String::from("NO INSTALL TARGET. CREATE A PARTITION FIRST WITH C.");
+                        status = tr("installer.no_target");
                         status_color = STATUS_ERR;
                         continue;
                     }
@@ -531,16 +547,13 @@ pub fn run() -> InstallerResult {
 
                     if !armed {
                         armed = true;
-                        status = format!(
-                            "DANGER: ENTER AGAIN TO FACTORY-RESET TARGET {} AND INSTALL.",
-                            selected + 1
-                        );
+                        status = trf("installer.danger", &[&(selected + 1)]);
                         status_color = STATUS_ERR;
                         continue;
                     }
 
 
-                    status = String::from("INSTALLING [0%] PREPARING TARGET. DO NOT POWER OFF.");
+                    status = trf("installer.installing", &[&0, &tr("installer.preparing")]);
                     status_color = STATUS_WARN;
                     draw_screen(
                         disks.as_slice(),
@@ -560,10 +573,13 @@ pub fn run() -> InstallerResult {
                         if pct == install_last_percent && install_last_detail.as_str() == detail {
                             return;
                         }
+                        if install_last_detail.as_str() != detail {
+                            crate::trace::instant("installer", "stage", detail);
+                        }
                         install_last_percent = pct;
                         install_last_detail.clear();
                         install_last_detail.push_str(detail);
-                        status = format!("INSTALLING [{}%] {}. DO NOT POWER OFF.", pct, detail);
+                        status = trf("installer.installing", &[&pct, &detail]);
                         status_color = STATUS_WARN;
                         draw_screen(
                             disks.as_slice(),
@@ -577,34 +593,34 @@ pub fn run() -> InstallerResult {
                         framebuffer::present();
                     };
 
-                    match install_to_partition(
-                        disk.handle,
-                        part.start_lba as u64,
-                        part.total_sectors as u64,
-                        paired_data_start_lba,
-                        payload.as_slice(),
-                        grub_assets.as_ref(),
-                        runtime_files.as_slice(),
-                        servort_files.as_slice(),
-                        &mut install_progress,
-                    ) {
+                    let install_outcome = {
+                        let _t = crate::trace::scope("installer", "install_to_partition");
+                        install_to_partition(
+                            disk.handle,
+                            part.start_lba as u64,
+                            part.total_sectors as u64,
+                            paired_data_start_lba,
+                            payload.as_slice(),
+                            grub_assets.as_ref(),
+                            shim_assets.as_ref(),
+                            runtime_files.as_slice(),
+                            servort_files.as_slice(),
+                            &mut install_progress,
+                        )
+                    };
+                    match install_outcome {
                         Ok(()) => {
                             unsafe {
-                                crate::fat32::GLOBAL_FAT.unmount();
+                                crate::fat32::GLOBAL_FAT.get_mut().unmount();
                             }
                             if runtime_files.is_empty() {
-                                status = String::from(
-                                    "INSTALL COMPLETE. BOOT + SYSTEM COPIED. LINUXRT NOT EMBEDDED IN BUILD.",
-                                );
+                                status = tr("installer.complete_no_runtime");
                             } else {
-                                status = format!(
-                                    "INSTALL COMPLETE. BOOT + SYSTEM + LINUXRT({}) + SERVORT({}) COPIED.",
-                                    runtime_files.len(),
-                                    servort_files.len()
-                                );
+                                status =
+                                    trf("installer.complete", &[&runtime_files.len(), &servort_files.len()]);
                             }
                             if grub_enabled {
-                                status.push_str(" GRUB MENU ENABLED.");
+                                status.push_str(tr("installer.grub_enabled").as_str());
                             }
                             status_color = STATUS_OK;
                             draw_screen(
@@ -618,11 +634,17 @@ pub fn run() -> InstallerResult {
                             );
                             framebuffer::present();
                             boot::stall(900_000);
+                            if let Some(data) = paired_data_partition_after_boot(disk, target.part_idx) {
+                                offer_data_encryption(disk.handle, data.start_lba as u64, data.total_sectors as u64);
+                            }
+                            if let (true, Some(shim)) = (secure_boot.enabled, shim_assets.as_ref()) {
+                                queue_mok_enrollment(shim.certificate.as_slice());
+                            }
                             return InstallerResult::Installed;
                         }
                         Err(err) => {
                             armed = false;
-                            status = format!("INSTALL ERROR: {}", err);
+                            status = trf("installer.install_error", &[&err]);
                             status_color = STATUS_ERR;
                         }
                     }
@@ -635,15 +657,157 @@ pub fn run() -> InstallerResult {
     }
 }
 
+/// Offer to encrypt the data partition after the install partition. Its
+/// contents are erased, so nothing happens unless the user types the same
+/// password twice; Esc keeps it as it is.
+fn offer_data_encryption(handle: Handle, start: u64, sectors: u64) {
+    let payload = sectors.saturating_sub(crate::crypt::HEADER_SECTORS);
+    if payload < DUAL_MIN_DATA_SECTORS as u64 || crate::crypt::has_header(handle, start, sectors) {
+        return;
+    }
+    let mut entries: [Vec<u8>; 2] = [Vec::new(), Vec::new()];
+    let mut field = 0usize;
+    let mut use_tpm = true;
+    let mut status = String::new();
+    let mut status_color = STATUS_WARN;
+    let (w, _) = framebuffer::dimensions();
+    loop {
+        framebuffer::clear(rgb(10, 14, 24));
+        framebuffer::rect(0, 0, w, 72, rgb(12, 34, 70));
+        framebuffer::draw_text_5x7(24, 22, tr("installer.crypt_title").as_str(), rgb(230, 240, 255));
+        framebuffer::draw_text_5x7(24, 96, tr("installer.crypt_intro").as_str(), STATUS_WARN);
+        for (index, entry) in entries.iter().enumerate() {
+            let key = if index == 0 { "installer.crypt_password" } else { "installer.crypt_confirm" };
+            let mut line = tr(key);
+            line.push(' ');
+            for _ in 0..entry.len() {
+                line.push('*');
+            }
+            if index == field {
+                line.push('_');
+            }
+            framebuffer::draw_text_5x7(24, 128 + index * 20, line.as_str(), rgb(230, 230, 230));
+        }
+        let tpm_key = if use_tpm { "installer.crypt_tpm_on" } else { "installer.crypt_tpm_off" };
+        framebuffer::draw_text_5x7(24, 176, tr(tpm_key).as_str(), rgb(196, 214, 241));
+        framebuffer::draw_text_5x7(24, 200, status.as_str(), status_color);
+        framebuffer::draw_text_5x7(24, 232, tr("installer.crypt_keys").as_str(), rgb(170, 190, 218));
+        framebuffer::present();
+
+        match input::poll_input_uefi() {
+            Some(RuntimeInput::Key(RuntimeKey::Esc)) => {
+                entries.iter_mut().for_each(|entry| entry.fill(0));
+                return;
+            }
+            Some(RuntimeInput::Key(RuntimeKey::F2)) => use_tpm = !use_tpm,
+            Some(RuntimeInput::Char(ch)) if ch.is_ascii() && !ch.is_ascii_control() => {
+                if entries[field].len() < 64 {
+                    entries[field].push(ch as u8);
+                }
+            }
+            Some(RuntimeInput::Backspace) => {
+                if let Some(last) = entries[field].last_mut() {
+                    *last = 0;
+                    entries[field].pop();
+                }
+            }
+            Some(RuntimeInput::Enter) => {
+                if entries[field].len() < crate::crypt::MIN_PASSWORD_LEN {
+                    status = trf("installer.crypt_short", &[&crate::crypt::MIN_PASSWORD_LEN]);
+                    status_color = STATUS_ERR;
+                } else if field == 0 {
+                    field = 1;
+                    status.clear();
+                } else if entries[0] != entries[1] {
+                    entries.iter_mut().for_each(|entry| {
+                        entry.fill(0);
+                        entry.clear();
+                    });
+                    field = 0;
+                    status = tr("installer.crypt_mismatch");
+                    status_color = STATUS_ERR;
+                } else {
+                    framebuffer::draw_text_5x7(24, 200, tr("installer.crypt_working").as_str(), STATUS_WARN);
+                    framebuffer::present();
+                    let password = core::str::from_utf8(&entries[0]).unwrap_or("");
+                    let result = crate::crypt::create(handle, start, sectors, password, use_tpm)
+                        .and_then(|payload| format_exfat_partition(handle, start, payload, "ZENOX DATA"));
+                    crate::crypt::forget(handle);
+                    entries.iter_mut().for_each(|entry| entry.fill(0));
+                    let (message, color) = match result {
+                        Ok(()) => (tr("installer.crypt_done"), STATUS_OK),
+                        Err(err) => (trf("installer.crypt_error", &[&err]), STATUS_ERR),
+                    };
+                    crate::println("Preboot installer: data partition encryption finished");
+                    crate::println(message.as_str());
+                    loop {
+                        framebuffer::rect(0, 196, w, 16, rgb(10, 14, 24));
+                        framebuffer::draw_text_5x7(24, 200, message.as_str(), color);
+                        framebuffer::draw_text_5x7(24, 256, tr("installer.crypt_continue").as_str(), STATUS_OK);
+                        framebuffer::present();
+                        if let Some(RuntimeInput::Enter) = input::poll_input_uefi() {
+                            return;
+                        }
+                        boot::stall(4_000);
+                    }
+                }
+            }
+            _ => {}
+        }
+        boot::stall(4_000);
+    }
+}
+
+/// Queue the signing certificate for MokManager and show the steps until
+/// the user presses Enter or Esc.
+fn queue_mok_enrollment(certificate: &[u8]) {
+    if crate::secureboot::certificate_enrolled(certificate) {
+        return;
+    }
+    let password = crate::secureboot::new_password();
+    let result = crate::secureboot::request_enrollment(certificate, &password);
+    crate::println("Preboot installer: MOK enrollment queued");
+    crate::println_num(result.is_ok() as u64);
+    let (w, _) = framebuffer::dimensions();
+    loop {
+        framebuffer::clear(rgb(10, 14, 24));
+        framebuffer::rect(0, 0, w, 72, rgb(12, 34, 70));
+        framebuffer::draw_text_5x7(24, 22, tr("installer.mok_title").as_str(), rgb(230, 240, 255));
+        let mut y = 96;
+        match result.as_ref() {
+            Ok(()) => {
+                framebuffer::draw_text_5x7(24, y, tr("installer.mok_intro").as_str(), STATUS_WARN);
+                y += 24;
+                for line in crate::secureboot::guidance_lines(&password).iter() {
+                    framebuffer::draw_text_5x7(24, y, line.as_str(), rgb(230, 230, 230));
+                    y += 16;
+                }
+            }
+            Err(err) => {
+                framebuffer::draw_text_5x7(24, y, trf("installer.mok_error", &[err]).as_str(), STATUS_ERR);
+                y += 16;
+            }
+        }
+        framebuffer::draw_text_5x7(24, y + 16, tr("installer.mok_continue").as_str(), STATUS_OK);
+        framebuffer::present();
+
+        match input::poll_input_uefi() {
+            Some(RuntimeInput::Enter) | Some(RuntimeInput::Key(RuntimeKey::Esc)) => return,
+            _ => {}
+        }
+        boot::stall(4_000);
+    }
+}
+
 fn draw_and_wait_error(err: &str) {
     crate::println("Preboot installer: fatal error");
     crate::println(err);
     loop {
         framebuffer::clear(rgb(10, 14, 24));
         framebuffer::rect(0, 0, 1600, 72, rgb(36, 14, 14));
-        framebuffer::draw_text_5x7(24, 22, "PREBOOT INSTALLER ERROR", rgb(255, 220, 220));
+        framebuffer::draw_text_5x7(24, 22, tr("installer.error_title").as_str(), rgb(255, 220, 220));
         framebuffer::draw_text_5x7(24, 96, err, STATUS_ERR);
-        framebuffer::draw_text_5x7(24, 120, "PRESS ESC TO CONTINUE TO SHELL.", rgb(230, 230, 230));
+        framebuffer::draw_text_5x7(24, 120, tr("installer.error_esc").as_str(), rgb(230, 230, 230));
         framebuffer::present();
 
         if let Some(RuntimeInput::Key(RuntimeKey::Esc)) = input::poll_input_uefi() {
@@ -661,11 +825,11 @@ fn draw_bootstrap_status(msg: &str) {
     framebuffer::draw_text_5x7(
         24,
         22,
-        "ZENOX OS PREBOOT INSTALLER",
+        tr("installer.bootstrap_title").as_str(),
         rgb(220, 235, 255),
     );
     framebuffer::draw_text_5x7(24, 110, msg, rgb(196, 214, 241));
-    framebuffer::draw_text_5x7(24, h.saturating_sub(38), "PLEASE WAIT...", rgb(170, 190, 218));
+    framebuffer::draw_text_5x7(24, h.saturating_sub(38), tr("installer.wait").as_str(), rgb(170, 190, 218));
     framebuffer::present();
 }
 
@@ -691,13 +855,13 @@ fn draw_screen(
     framebuffer::draw_text_5x7(
         24,
         20,
-        "ZENOX OS GRAPHICAL INSTALLER (PRE-BOOT)",
+        tr("installer.title").as_str(),
         rgb(220, 235, 255),
     );
     framebuffer::draw_text_5x7(
         24,
         40,
-        "CAN RESIZE + CREATE PARTITIONS, THEN INSTALL SYSTEM BOOT PACKAGE",
+        tr("installer.subtitle").as_str(),
         rgb(168, 198, 235),
     );
 
@@ -707,14 +871,9 @@ fn draw_screen(
     let panel_h = h.saturating_sub(180);
     framebuffer::rect(panel_x, panel_y, panel_w, panel_h, rgb(20, 28, 46));
     framebuffer::rect(panel_x, panel_y, panel_w, 28, rgb(26, 44, 74));
-    framebuffer::draw_text_5x7(panel_x + 12, panel_y + 10, "INSTALL TARGETS", rgb(229, 239, 255));
+    framebuffer::draw_text_5x7(panel_x + 12, panel_y + 10, tr("installer.targets").as_str(), rgb(229, 239, 255));
 
-    let info = format!(
-        "PAYLOAD {} KB   DISKS {}   TARGETS {}",
-        payload_len / 1024,
-        disks.len(),
-        targets.len()
-    );
+    let info = trf("installer.summary", &[&(payload_len / 1024), &disks.len(), &targets.len()]);
     framebuffer::draw_text_5x7(panel_x + 190, panel_y + 10, info.as_str(), rgb(180, 210, 250));
 
     if targets.is_empty() {
@@ -722,14 +881,14 @@ fn draw_screen(
             framebuffer::draw_text_5x7(
                 panel_x + 14,
                 panel_y + 48,
-                "NO INTERNAL DISKS DETECTED.",
+                tr("installer.no_disks").as_str(),
                 STATUS_ERR,
             );
         } else {
             framebuffer::draw_text_5x7(
                 panel_x + 14,
                 panel_y + 48,
-                "NO PARTITIONS FOUND. PRESS C TO CREATE 16 GIB FAT32 BOOT + REST EXFAT DATA.",
+                tr("installer.no_partitions").as_str(),
                 STATUS_WARN,
             );
         }
@@ -776,7 +935,7 @@ fn draw_screen(
         }
 
         if targets.len() > count {
-            let more = format!("{} MORE TARGETS NOT SHOWN", targets.len() - count);
+            let more = trf("installer.more_targets", &[&(targets.len() - count)]);
             framebuffer::draw_text_5x7(panel_x + 14, panel_y + panel_h - 46, more.as_str(), rgb(170, 180, 196));
         }
     }
@@ -785,13 +944,13 @@ fn draw_screen(
     framebuffer::draw_text_5x7(
         panel_x + 12,
         help_y,
-        "N/P MOVE  +/- RESIZE  C CREATE/SPLIT  R RELOAD  1-9 SELECT  ENTER INSTALL  ESC SKIP",
+        tr("installer.keys").as_str(),
         rgb(191, 209, 236),
     );
     framebuffer::draw_text_5x7(
         panel_x + 12,
         help_y + 14,
-        "C USES FREE SPACE; ON DATA/OTHER, C AGAIN ERASES ONLY SELECTED PARTITION. STEP = 128 MIB.",
+        tr("installer.keys_note").as_str(),
         rgb(170, 190, 218),
     );
 
@@ -799,7 +958,7 @@ fn draw_screen(
         framebuffer::draw_text_5x7(
             panel_x + 12,
             help_y + 30,
-            "CONFIRMATION ARMED: ENTER AGAIN WILL FACTORY-RESET THE SELECTED PARTITION.",
+            tr("installer.armed").as_str(),
             STATUS_ERR,
         );
     }
@@ -873,6 +1032,69 @@ fn discover_internal_disks() -> Vec<InternalDisk> {
     out
 }
 
+/// The partition tables of the internal disks, as the installer reads them,
+/// for the `parts` command. Nothing is written.
+pub(crate) fn partition_table_lines() -> Vec<String> {
+    let mut handles = boot::find_handles::<BlockIO>().unwrap_or_default();
+    handles.sort_unstable();
+    let mut out = Vec::new();
+    let mut disk = 0usize;
+    for handle in handles.iter().copied() {
+        let Some((block_size, total_logical_sectors)) = describe_physical_disk(handle) else {
+            continue;
+        };
+        disk += 1;
+        let mut sector0 = [0u8; LOGICAL_SECTOR_SIZE];
+        let mbr = if read_sector_from_uefi_handle(handle, 0, &mut sector0) && sector0[510] == 0x55 && sector0[511] == 0xAA
+        {
+            parse_mbr_partitions(&sector0)
+        } else {
+            [MbrPartition::default(); 4]
+        };
+        let (scheme, partitions) = if mbr.iter().any(|p| p.part_type == 0xEE) {
+            match parse_gpt_partitions(handle, block_size, total_logical_sectors) {
+                Ok(parts) => ("GPT", parts),
+                Err(err) => {
+                    out.push(format!("Disco {}: GPT ilegible ({})", disk, err));
+                    continue;
+                }
+            }
+        } else {
+            ("MBR", mbr.to_vec())
+        };
+        out.push(format!(
+            "Disco {}: {} {} MiB, sector {} bytes",
+            disk,
+            scheme,
+            total_logical_sectors / 2048,
+            block_size
+        ));
+        let mut shown = 0usize;
+        for (index, part) in partitions.iter().enumerate() {
+            if !part.is_used() || !is_visible_partition_for_selection(PartitionScheme::Mbr, part.part_type) {
+                continue;
+            }
+            shown += 1;
+            out.push(format!(
+                "  {}) LBA {:>10}  {:>8} MiB  tipo {:02X}  {}{}",
+                index + 1,
+                part.start_lba,
+                part.total_sectors / 2048,
+                part.part_type,
+                partition_role_label(part.part_type),
+                if part.boot == 0x80 { "  activa" } else { "" }
+            ));
+        }
+        if shown == 0 {
+            out.push(String::from("  (sin particiones)"));
+        }
+    }
+    if disk == 0 {
+        out.push(String::from("No hay discos internos escribibles."));
+    }
+    out
+}
+
 fn current_boot_device_handle() -> Option<Handle> {
     let params = OpenProtocolParams {
         handle: boot::image_handle(),
@@ -3445,7 +3667,7 @@ fn load_runtime_from_global_fat(allow_removable: bool) -> Vec<RuntimeInstallFile
         }
     }
 
-    let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
     if !fat.init() {
         return Vec::new();
     }
@@ -3737,7 +3959,7 @@ fn load_servort_from_global_fat(allow_removable: bool) -> Vec<ServortInstallFile
         }
     }
 
-    let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
     if !fat.init() {
         return Vec::new();
     }
@@ -4175,6 +4397,7 @@ fn install_to_partition<F>(
     paired_data_start_lba: Option<u64>,
     payload: &[u8],
     grub_assets: Option<&GrubInstallAssets>,
+    shim_assets: Option<&crate::secureboot::ShimAssets>,
     runtime_files: &[RuntimeInstallFile],
     servort_files: &[ServortInstallFile],
     progress: &mut F,
@@ -4215,11 +4438,22 @@ where
     let runtime_enabled = !runtime_files.is_empty();
     let servort_enabled = !servort_files.is_empty();
     let grub_enabled = grub_assets.is_some();
-    let boot_payload = if let Some(grub) = grub_assets {
+    // Under shim, BOOTX64.EFI is shim and it runs GRUBX64.EFI from the same
+    // directory: the signed kernel image.
+    let boot_payload = if let Some(shim) = shim_assets {
+        shim.shim.as_slice()
+    } else if let Some(grub) = grub_assets {
         grub.efi_payload.as_slice()
     } else {
         payload
     };
+    let redux_payload = shim_assets.map(|shim| shim.signed_payload.as_slice()).unwrap_or(payload);
+    let mut shim_files: Vec<([u8; 11], &[u8])> = Vec::new();
+    if let Some(shim) = shim_assets {
+        shim_files.push((*b"GRUBX64 EFI", shim.signed_payload.as_slice()));
+        shim_files.push((*b"MMX64   EFI", shim.mok_manager.as_slice()));
+        shim_files.push((*b"REDUX   CER", shim.certificate.as_slice()));
+    }
     let grub_config_payload = grub_assets.map(|grub| grub.config_payload.as_slice());
 
     let startup_content = b"\\EFI\\BOOT\\BOOTX64.EFI\r\n";
@@ -4236,7 +4470,9 @@ where
     let mut readme_text = String::from(
         "Zenox OS installed on internal storage.\r\nBoot path: \\EFI\\BOOT\\BOOTX64.EFI\r\n",
     );
-    if grub_enabled {
+    if shim_assets.is_some() {
+        readme_text.push_str("Boot manager: shim (Secure Boot). Signed image: \\EFI\\BOOT\\GRUBX64.EFI\r\n");
+    } else if grub_enabled {
         readme_text.push_str("Boot manager: GRUB.\r\n");
     }
     readme_text.push_str(format!("Boot LBA: {}\r\n", partition_start_lba).as_str());
@@ -4295,7 +4531,10 @@ where
         + if grub_enabled { 1 } else { 0 };
     let efi_dir_entries = 1usize + if grub_enabled { 1 } else { 0 };
     let efi_grub_dir_entries = if grub_enabled { 1usize } else { 0usize };
-    let boot_dir_entries = 1usize + if grub_enabled { 2 } else { 0 };
+    let boot_dir_entries = 1usize
+        + if grub_enabled || shim_assets.is_some() { 1 } else { 0 }
+        + if grub_enabled { 1 } else { 0 }
+        + shim_files.len();
     let linuxrt_root_dir_entries = if runtime_enabled { 6usize } else { 0usize };
     let linuxrt_usr_dir_entries = if runtime_enabled { 3usize } else { 0usize };
     let servort_root_dir_entries = if servort_enabled {
@@ -4436,12 +4675,17 @@ where
     let startup_file = allocate_cluster_chain(&mut next_cluster, startup_clusters)?;
     let config_file = allocate_cluster_chain(&mut next_cluster, config_clusters)?;
     let readme_file = allocate_cluster_chain(&mut next_cluster, readme_clusters)?;
-    let redux_efi_file = if grub_enabled {
-        let redux_clusters = cluster_count_for_bytes(payload.len(), cluster_size);
+    let redux_efi_file = if grub_enabled || shim_assets.is_some() {
+        let redux_clusters = cluster_count_for_bytes(redux_payload.len(), cluster_size);
         Some(allocate_cluster_chain(&mut next_cluster, redux_clusters)?)
     } else {
         None
     };
+    let mut shim_file_chains = Vec::with_capacity(shim_files.len());
+    for (_, content) in shim_files.iter() {
+        let clusters = cluster_count_for_bytes(content.len(), cluster_size);
+        shim_file_chains.push(allocate_cluster_chain(&mut next_cluster, clusters)?);
+    }
     let grub_cfg_file = if let Some(cfg) = grub_config_payload {
         let grub_cfg_clusters = cluster_count_for_bytes(cfg.len(), cluster_size);
         Some(allocate_cluster_chain(&mut next_cluster, grub_cfg_clusters)?)
@@ -4710,6 +4954,15 @@ where
             file.cluster_count,
         )?;
     }
+    for file in shim_file_chains.iter() {
+        write_chain_entries(
+            disk_handle,
+            fat_start,
+            sectors_per_fat,
+            file.first_cluster,
+            file.cluster_count,
+        )?;
+    }
     if let Some(file) = grub_cfg_file {
         write_chain_entries(
             disk_handle,
@@ -4861,7 +5114,15 @@ where
             short_name: *b"REDUX64 EFI",
             attr: 0x20,
             first_cluster: file.first_cluster,
-            size: payload.len() as u32,
+            size: redux_payload.len() as u32,
+        });
+    }
+    for ((short_name, content), file) in shim_files.iter().zip(shim_file_chains.iter()) {
+        boot_entries.push(DirEntryLayout {
+            short_name: *short_name,
+            attr: 0x20,
+            first_cluster: file.first_cluster,
+            size: content.len() as u32,
         });
     }
     if let (Some(file), Some(cfg)) = (grub_cfg_file, grub_config_payload) {
@@ -5147,7 +5408,17 @@ where
             sectors_per_cluster,
             file.first_cluster,
             file.cluster_count,
-            payload,
+            redux_payload,
+        )?;
+    }
+    for ((_, content), file) in shim_files.iter().zip(shim_file_chains.iter()) {
+        write_cluster_chain_data(
+            disk_handle,
+            data_start,
+            sectors_per_cluster,
+            file.first_cluster,
+            file.cluster_count,
+            content,
         )?;
     }
     if let (Some(file), Some(cfg)) = (grub_cfg_file, grub_config_payload) {
@@ -6069,7 +6340,7 @@ fn write_cluster_chain_data_from_fat_source(
         );
     }
 
-    let fat = unsafe { &mut crate::fat32::GLOBAL_FAT };
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
     if !fat.init() {
         return Err("FAILED TO INIT GLOBAL FAT FOR SERVORT SOURCE.");
     }
@@ -6289,10 +6560,13 @@ fn read_sector_from_uefi_handle(
         return false;
     }
     buffer.copy_from_slice(&scratch[offset..offset + LOGICAL_SECTOR_SIZE]);
+    crate::crypt::decrypt(handle, lba, buffer);
     true
 }
 
 fn write_sector_to_uefi_handle(handle: Handle, lba: u64, buffer: &[u8; LOGICAL_SECTOR_SIZE]) -> bool {
+    let encrypted = crate::crypt::encrypt(handle, lba, buffer);
+    let buffer = encrypted.as_deref().unwrap_or(buffer.as_slice());
     let params = OpenProtocolParams {
         handle,
         agent: boot::image_handle(),
@@ -0,0 +1,788 @@
+//! Printing to IPP Everywhere network printers.
+//!
+//! Printers come from mDNS (`_ipp._tcp.local`, see `net::mdns`) or from
+//! `print add <uri>`, and are remembered until reboot; `print.default` names
+//! the one the print dialog preselects. A text `Document` goes out as PDF
+//! when the printer takes it, and everything else as PWG raster, the one
+//! format every IPP Everywhere printer accepts. Raster text is drawn with
+//! the 5x7 font, so it prints in upper case.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use redux_netparse::dns::txt_value;
+use redux_netparse::ipp::ipp_job_state_text;
+use redux_netparse::pwg::{encode_pwg_page, PwgPage, PWG_SYNC};
+
+use crate::config::ConfigValue;
+use crate::net::ipp::PrinterInfo;
+use crate::net::mdns::MdnsService;
+use crate::spinlock::SpinLock;
+
+const SERVICE: &str = "_ipp._tcp.local";
+const DEFAULT_KEY: &str = "print.default";
+const DISCOVER_TIMEOUT_MS: u64 = 3_000;
+const USER: &str = "redux";
+const MAX_PRINTERS: usize = 16;
+const MAX_JOBS: usize = 32;
+/// Page layout shared by the PDF and raster paths, in points: Courier
+/// 10 pt (6 pt advance) on a 12 pt leading inside 50 pt margins.
+const MARGIN_PT: u32 = 50;
+const FONT_PT: u32 = 10;
+const ADVANCE_PT: u32 = 6;
+const LEADING_PT: u32 = 12;
+const COLUMNS: usize = 82;
+const TAB_WIDTH: usize = 4;
+const PREFERRED_DPI: u32 = 150;
+const FALLBACK_DPI: u32 = 300;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Media {
+    pub name: &'static str,
+    pub width_pt: u32,
+    pub height_pt: u32,
+}
+
+pub const MEDIA_A4: Media = Media {
+    name: "iso_a4_210x297mm",
+    width_pt: 595,
+    height_pt: 842,
+};
+pub const MEDIA_LETTER: Media = Media {
+    name: "na_letter_8.5x11in",
+    width_pt: 612,
+    height_pt: 792,
+};
+
+impl Media {
+    pub fn lines_per_page(&self) -> usize {
+        ((self.height_pt - 2 * MARGIN_PT) / LEADING_PT) as usize
+    }
+}
+
+pub enum Document {
+    Text {
+        title: String,
+        text: String,
+    },
+    /// 0xRRGGBB pixels, row by row.
+    Image {
+        title: String,
+        width: u32,
+        height: u32,
+        pixels: Vec<u32>,
+    },
+}
+
+impl Document {
+    pub fn title(&self) -> &str {
+        match self {
+            Document::Text { title, .. } | Document::Image { title, .. } => title.as_str(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Printer {
+    name: String,
+    uri: String,
+    /// Get-Printer-Attributes answer, fetched on first use.
+    info: Option<PrinterInfo>,
+}
+
+#[derive(Clone)]
+pub struct PrintJob {
+    pub id: u32,
+    pub printer: String,
+    pub title: String,
+    pub format: &'static str,
+    uri: String,
+    remote_id: i32,
+    pub state: i32,
+}
+
+struct State {
+    printers: Vec<Printer>,
+    jobs: Vec<PrintJob>,
+    next_job: u32,
+}
+
+static STATE: SpinLock<State> = SpinLock::new(State {
+    printers: Vec::new(),
+    jobs: Vec::new(),
+    next_job: 1,
+});
+
+/// `ipp://a.b.c.d:port/<rp>` for a discovered service; `rp` defaults to
+/// `ipp/print` as IPP Everywhere asks.
+pub fn service_uri(service: &MdnsService) -> String {
+    let rp = txt_value(&service.txt, "rp")
+        .unwrap_or("ipp/print")
+        .trim_start_matches('/');
+    let [a, b, c, d] = service.addr;
+    alloc::format!("ipp://{}.{}.{}.{}:{}/{}", a, b, c, d, service.port, rp)
+}
+
+pub fn printer_names() -> Vec<String> {
+    STATE.lock().printers.iter().map(|p| p.name.clone()).collect()
+}
+
+/// Index of the `print.default` printer in `names`, or 0.
+pub fn default_index(names: &[String]) -> usize {
+    let default = crate::config::get_str(DEFAULT_KEY, "");
+    names
+        .iter()
+        .position(|n| n.eq_ignore_ascii_case(default.as_str()))
+        .unwrap_or(0)
+}
+
+/// Remember a printer; an already known URI only gets its name updated.
+/// Returns the name it is listed under.
+fn add_printer(name: &str, uri: &str) -> Result<String, &'static str> {
+    let mut state = STATE.lock();
+    if let Some(existing) = state.printers.iter_mut().find(|p| p.uri.eq_ignore_ascii_case(uri)) {
+        existing.name = String::from(name);
+        return Ok(existing.name.clone());
+    }
+    if state.printers.len() >= MAX_PRINTERS {
+        return Err("demasiadas impresoras");
+    }
+    let mut unique = String::from(name);
+    let mut n = 2;
+    while state.printers.iter().any(|p| p.name.eq_ignore_ascii_case(&unique)) {
+        unique = alloc::format!("{} ({})", name, n);
+        n += 1;
+    }
+    state.printers.push(Printer {
+        name: unique.clone(),
+        uri: String::from(uri),
+        info: None,
+    });
+    Ok(unique)
+}
+
+/// A printer by name (ignoring case) or by its 1-based position.
+fn lookup(key: &str) -> Option<Printer> {
+    let state = STATE.lock();
+    if let Ok(index) = key.parse::<usize>() {
+        return state.printers.get(index.checked_sub(1)?).cloned();
+    }
+    state
+        .printers
+        .iter()
+        .find(|p| p.name.eq_ignore_ascii_case(key))
+        .cloned()
+}
+
+/// Browse for printers and add them. Returns how many answered.
+pub fn discover(pump_ui: &mut impl FnMut()) -> usize {
+    let found = crate::net::mdns::browse(SERVICE, DISCOVER_TIMEOUT_MS, pump_ui);
+    for service in found.iter() {
+        let _ = add_printer(service.name.as_str(), service_uri(service).as_str());
+    }
+    found.len()
+}
+
+/// Printer attributes, queried once and cached.
+fn capabilities(printer: &Printer, pump_ui: &mut impl FnMut()) -> Result<PrinterInfo, String> {
+    if let Some(info) = printer.info.clone() {
+        return Ok(info);
+    }
+    let info = crate::net::ipp::get_printer_attributes(printer.uri.as_str(), pump_ui)?;
+    if let Some(p) = STATE.lock().printers.iter_mut().find(|p| p.uri == printer.uri) {
+        p.info = Some(info.clone());
+    }
+    Ok(info)
+}
+
+pub fn media_for(info: &PrinterInfo) -> Media {
+    if info.media_default.starts_with("na_letter") {
+        MEDIA_LETTER
+    } else {
+        MEDIA_A4
+    }
+}
+
+/// The raster resolution: 150 dpi when offered (plenty for 5x7 text and
+/// screenshots), else the lowest offered, else 300.
+pub fn raster_dpi(info: &PrinterInfo) -> u32 {
+    if info.resolutions.contains(&PREFERRED_DPI) {
+        return PREFERRED_DPI;
+    }
+    info.resolutions.iter().copied().min().unwrap_or(FALLBACK_DPI)
+}
+
+/// Whether a raster page goes out as sRGB (else sGray).
+fn raster_color(info: &PrinterInfo, want_color: bool) -> bool {
+    let rgb = info.supports_raster_type("srgb_8");
+    rgb && (want_color || !info.supports_raster_type("sgray_8"))
+}
+
+/// Text split into pages of `lines_per_page` lines of at most `COLUMNS`
+/// characters. Tabs become spaces and a form feed starts a new page.
+pub fn paginate(text: &str, lines_per_page: usize) -> Vec<Vec<String>> {
+    let mut pages: Vec<Vec<String>> = alloc::vec![Vec::new()];
+    let push = |pages: &mut Vec<Vec<String>>, line: String| {
+        if pages.last().map(|p| p.len() >= lines_per_page).unwrap_or(true) {
+            pages.push(Vec::new());
+        }
+        if let Some(page) = pages.last_mut() {
+            page.push(line);
+        }
+    };
+    for raw in text.split('\n') {
+        let raw = raw.trim_end_matches('\r');
+        let single = !raw.contains('\x0c');
+        for (i, part) in raw.split('\x0c').enumerate() {
+            if i > 0 && pages.last().map(|p| !p.is_empty()).unwrap_or(false) {
+                pages.push(Vec::new());
+            }
+            let mut line = String::new();
+            let mut cols = 0usize;
+            for ch in part.chars() {
+                let (ch, count) = if ch == '\t' {
+                    (' ', TAB_WIDTH - cols % TAB_WIDTH)
+                } else {
+                    (ch, 1)
+                };
+                for _ in 0..count {
+                    if cols == COLUMNS {
+                        push(&mut pages, core::mem::take(&mut line));
+                        cols = 0;
+                    }
+                    line.push(if ch.is_control() { ' ' } else { ch });
+                    cols += 1;
+                }
+            }
+            if single || !line.is_empty() {
+                push(&mut pages, line);
+            }
+        }
+    }
+    while pages.len() > 1 && pages.last().map(|p| p.iter().all(|l| l.is_empty())).unwrap_or(false) {
+        pages.pop();
+    }
+    pages
+}
+
+/// A PDF string literal: WinAnsi covers Latin-1, anything else prints as '?'.
+fn pdf_string(out: &mut Vec<u8>, text: &str) {
+    out.push(b'(');
+    for ch in text.chars() {
+        match ch {
+            '(' | ')' | '\\' => {
+                out.push(b'\\');
+                out.push(ch as u8);
+            }
+            ' '..='~' => out.push(ch as u8),
+            '\u{a0}'..='\u{ff}' => out.extend_from_slice(alloc::format!("\\{:03o}", ch as u32).as_bytes()),
+            _ => out.push(b'?'),
+        }
+    }
+    out.push(b')');
+}
+
+/// Start the next numbered object, recording its offset for the xref table.
+fn pdf_object(out: &mut Vec<u8>, offsets: &mut Vec<usize>) {
+    offsets.push(out.len());
+    out.extend_from_slice(alloc::format!("{} 0 obj\n", offsets.len()).as_bytes());
+}
+
+/// A minimal PDF 1.4: one Courier font, one content stream per page.
+pub fn text_pdf(title: &str, pages: &[Vec<String>], media: Media) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(1024 + pages.len() * COLUMNS * media.lines_per_page());
+    let mut offsets: Vec<usize> = Vec::new();
+    out.extend_from_slice(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n");
+    pdf_object(&mut out, &mut offsets);
+    out.extend_from_slice(b"<< /Type /Catalog /Pages 2 0 R >>\nendobj\n");
+    pdf_object(&mut out, &mut offsets);
+    let kids: Vec<String> = (0..pages.len()).map(|i| alloc::format!("{} 0 R", 5 + 2 * i)).collect();
+    out.extend_from_slice(
+        alloc::format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>\nendobj\n",
+            kids.join(" "),
+            pages.len()
+        )
+        .as_bytes(),
+    );
+    pdf_object(&mut out, &mut offsets);
+    out.extend_from_slice(b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>\nendobj\n");
+    pdf_object(&mut out, &mut offsets);
+    out.extend_from_slice(b"<< /Title ");
+    pdf_string(&mut out, title);
+    out.extend_from_slice(b" /Producer (ReduxOS) >>\nendobj\n");
+
+    for (i, lines) in pages.iter().enumerate() {
+        pdf_object(&mut out, &mut offsets);
+        out.extend_from_slice(
+            alloc::format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>\nendobj\n",
+                media.width_pt,
+                media.height_pt,
+                6 + 2 * i
+            )
+            .as_bytes(),
+        );
+        let mut content = alloc::format!(
+            "BT\n/F1 {} Tf\n{} TL\n{} {} Td\n",
+            FONT_PT,
+            LEADING_PT,
+            MARGIN_PT,
+            media.height_pt - MARGIN_PT - FONT_PT
+        )
+        .into_bytes();
+        for line in lines.iter() {
+            pdf_string(&mut content, line);
+            content.extend_from_slice(b" Tj T*\n");
+        }
+        content.extend_from_slice(b"ET\n");
+        pdf_object(&mut out, &mut offsets);
+        out.extend_from_slice(alloc::format!("<< /Length {} >>\nstream\n", content.len()).as_bytes());
+        out.extend_from_slice(&content);
+        out.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    let xref = out.len();
+    out.extend_from_slice(alloc::format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1).as_bytes());
+    for offset in offsets.iter() {
+        out.extend_from_slice(alloc::format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    out.extend_from_slice(
+        alloc::format!(
+            "trailer\n<< /Size {} /Root 1 0 R /Info 4 0 R >>\nstartxref\n{}\n%%EOF\n",
+            offsets.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    out
+}
+
+fn pwg_page(media: Media, dpi: u32, color: bool, total_pages: u32) -> PwgPage {
+    PwgPage {
+        width: media.width_pt * dpi / 72,
+        height: media.height_pt * dpi / 72,
+        dpi,
+        color,
+        copies: 1,
+        total_pages,
+        page_size_pt: (media.width_pt, media.height_pt),
+        media: media.name,
+    }
+}
+
+/// Text pages as PWG raster (sGray unless the printer only takes sRGB).
+pub fn text_raster(pages: &[Vec<String>], media: Media, dpi: u32, color: bool) -> Vec<u8> {
+    let advance = (dpi * ADVANCE_PT / 72) as usize;
+    let leading = (dpi * LEADING_PT / 72) as usize;
+    let margin = (dpi * MARGIN_PT / 72) as usize;
+    let scale = (advance / 6).max(1);
+    let mut out = PWG_SYNC.to_vec();
+    for lines in pages.iter() {
+        let page = pwg_page(media, dpi, color, pages.len() as u32);
+        let bpp = page.bytes_per_pixel();
+        let glyphs: Vec<Vec<[u8; 7]>> = lines
+            .iter()
+            .map(|l| l.chars().map(crate::font::glyph_5x7).collect())
+            .collect();
+        let mut row = |y: u32, line: &mut [u8]| {
+            line.fill(0xFF);
+            let Some(y) = (y as usize).checked_sub(margin) else {
+                return;
+            };
+            let glyph_row = (y % leading) / scale;
+            let Some(chars) = glyphs.get(y / leading).filter(|_| glyph_row < 7) else {
+                return;
+            };
+            for (col, glyph) in chars.iter().enumerate() {
+                let bits = glyph[glyph_row];
+                for gx in 0..5 * scale {
+                    if bits & (0x10 >> (gx / scale)) != 0 {
+                        let x = margin + col * advance + gx;
+                        if let Some(pixel) = line.get_mut(x * bpp..(x + 1) * bpp) {
+                            pixel.fill(0);
+                        }
+                    }
+                }
+            }
+        };
+        out.extend_from_slice(&encode_pwg_page(&page, &mut row));
+    }
+    out
+}
+
+/// One image page, scaled to fit inside the margins and centered
+/// horizontally.
+pub fn image_raster(width: u32, height: u32, pixels: &[u32], media: Media, dpi: u32, color: bool) -> Vec<u8> {
+    let page = pwg_page(media, dpi, color, 1);
+    let bpp = page.bytes_per_pixel();
+    let margin = dpi * MARGIN_PT / 72;
+    let area_w = page.width.saturating_sub(2 * margin).max(1) as u64;
+    let area_h = page.height.saturating_sub(2 * margin).max(1) as u64;
+    let (iw, ih) = (width.max(1) as u64, height.max(1) as u64);
+    let (tw, th) = if iw * area_h > ih * area_w {
+        (area_w, (ih * area_w / iw).max(1))
+    } else {
+        ((iw * area_h / ih).max(1), area_h)
+    };
+    let left = margin as u64 + (area_w - tw) / 2;
+    let top = margin as u64;
+    let mut row = |y: u32, line: &mut [u8]| {
+        line.fill(0xFF);
+        let y = y as u64;
+        if y < top || y >= top + th || pixels.len() < width as usize * height as usize {
+            return;
+        }
+        let sy = (y - top) * ih / th;
+        for x in 0..tw {
+            let sx = x * iw / tw;
+            let rgb = pixels[(sy * iw + sx) as usize];
+            let (r, g, b) = ((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8);
+            let at = (left + x) as usize * bpp;
+            if color {
+                line[at..at + 3].copy_from_slice(&[r, g, b]);
+            } else {
+                line[at] = ((r as u32 * 77 + g as u32 * 150 + b as u32 * 29) >> 8) as u8;
+            }
+        }
+    };
+    let mut out = PWG_SYNC.to_vec();
+    out.extend_from_slice(&encode_pwg_page(&page, &mut row));
+    out
+}
+
+/// The document in a format the printer takes, with its MIME type.
+pub fn render(document: &Document, info: &PrinterInfo) -> Result<(&'static str, Vec<u8>), String> {
+    let media = media_for(info);
+    let raster = info.supports_format("image/pwg-raster") || info.formats.is_empty();
+    match document {
+        Document::Text { title, text } => {
+            let pages = paginate(text, media.lines_per_page());
+            if info.supports_format("application/pdf") {
+                Ok(("application/pdf", text_pdf(title, &pages, media)))
+            } else if raster {
+                let dpi = raster_dpi(info);
+                Ok((
+                    "image/pwg-raster",
+                    text_raster(&pages, media, dpi, raster_color(info, false)),
+                ))
+            } else {
+                Err(String::from("la impresora no acepta PDF ni PWG raster"))
+            }
+        }
+        Document::Image {
+            width, height, pixels, ..
+        } => {
+            if !raster {
+                return Err(String::from("la impresora no acepta PWG raster"));
+            }
+            let dpi = raster_dpi(info);
+            let color = raster_color(info, info.color);
+            Ok((
+                "image/pwg-raster",
+                image_raster(*width, *height, pixels, media, dpi, color),
+            ))
+        }
+    }
+}
+
+/// Render and submit `document` to the printer called `printer` (name or
+/// 1-based number). Returns the local job number.
+pub fn print_document(
+    printer: &str,
+    document: &Document,
+    copies: u32,
+    pump_ui: &mut impl FnMut(),
+) -> Result<u32, String> {
+    let printer = lookup(printer).ok_or_else(|| String::from("impresora desconocida"))?;
+    let info = capabilities(&printer, pump_ui)?;
+    let (format, data) = render(document, &info)?;
+    let (remote_id, job_state) = crate::net::ipp::print_job(
+        printer.uri.as_str(),
+        document.title(),
+        USER,
+        format,
+        copies.max(1),
+        &data,
+        pump_ui,
+    )?;
+    crate::klog::log(
+        crate::klog::Level::Info,
+        alloc::format!(
+            "print: '{}' -> {} ({}, {} bytes, job {})",
+            document.title(),
+            printer.name,
+            format,
+            data.len(),
+            remote_id
+        )
+        .as_str(),
+    );
+    let mut state = STATE.lock();
+    let id = state.next_job;
+    state.next_job += 1;
+    if state.jobs.len() >= MAX_JOBS {
+        state.jobs.remove(0);
+    }
+    state.jobs.push(PrintJob {
+        id,
+        printer: printer.name.clone(),
+        title: String::from(document.title()),
+        format,
+        uri: printer.uri.clone(),
+        remote_id,
+        state: job_state,
+    });
+    Ok(id)
+}
+
+pub fn jobs() -> Vec<PrintJob> {
+    STATE.lock().jobs.clone()
+}
+
+/// Ask the printers for the state of every job that has not finished.
+fn refresh_jobs(pump_ui: &mut impl FnMut()) {
+    let pending: Vec<PrintJob> = jobs().into_iter().filter(|j| j.state < 7).collect();
+    for job in pending.iter() {
+        if let Ok(state) = crate::net::ipp::get_job_state(job.uri.as_str(), job.remote_id, pump_ui) {
+            if let Some(j) = STATE.lock().jobs.iter_mut().find(|j| j.id == job.id) {
+                j.state = state;
+            }
+        }
+    }
+}
+
+fn cancel(id: u32, pump_ui: &mut impl FnMut()) -> Result<(), String> {
+    let job = jobs()
+        .into_iter()
+        .find(|j| j.id == id)
+        .ok_or_else(|| String::from("trabajo desconocido"))?;
+    crate::net::ipp::cancel_job(job.uri.as_str(), job.remote_id, USER, pump_ui)?;
+    if let Some(j) = STATE.lock().jobs.iter_mut().find(|j| j.id == id) {
+        j.state = 7;
+    }
+    Ok(())
+}
+
+fn list_lines() -> Vec<String> {
+    let state = STATE.lock();
+    if state.printers.is_empty() {
+        return alloc::vec![String::from(
+            "print: no hay impresoras; usa 'print discover' o 'print add ipp://<host>/ipp/print'"
+        )];
+    }
+    let default = crate::config::get_str(DEFAULT_KEY, "");
+    let mut out = alloc::vec![String::from("Impresoras:")];
+    for (i, p) in state.printers.iter().enumerate() {
+        let mark = if p.name.eq_ignore_ascii_case(default.as_str()) {
+            " (predeterminada)"
+        } else {
+            ""
+        };
+        out.push(alloc::format!("  {}. {}  {}{}", i + 1, p.name, p.uri, mark));
+    }
+    out
+}
+
+fn info_lines(printer: &Printer, info: &PrinterInfo) -> Vec<String> {
+    let resolutions: Vec<String> = info.resolutions.iter().map(|r| alloc::format!("{}", r)).collect();
+    alloc::vec![
+        alloc::format!("{} ({})", printer.name, printer.uri),
+        alloc::format!("  modelo: {}", info.make_model),
+        alloc::format!(
+            "  estado: {} {}",
+            crate::net::ipp::printer_state_text(info.state),
+            info.state_reasons.join(",")
+        ),
+        alloc::format!("  formatos: {}", info.formats.join(", ")),
+        alloc::format!(
+            "  raster: {} a {} dpi",
+            info.raster_types.join(", "),
+            resolutions.join("/")
+        ),
+        alloc::format!(
+            "  color: {}  papel: {}",
+            if info.color { "si" } else { "no" },
+            info.media_default
+        ),
+    ]
+}
+
+fn test_page(printer: &Printer) -> Document {
+    let mut text = String::from("ReduxOS - pagina de prueba\n\n");
+    text.push_str(alloc::format!("Impresora: {}\nURI: {}\n\n", printer.name, printer.uri).as_str());
+    text.push_str(
+        " !\"#$%&'()*+,-./0123456789:;<=>?@\nABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`\nabcdefghijklmnopqrstuvwxyz{|}~\n",
+    );
+    Document::Text {
+        title: String::from("Pagina de prueba"),
+        text,
+    }
+}
+
+pub fn command_lines(args: &str, pump_ui: &mut impl FnMut()) -> Vec<String> {
+    let args = args.trim();
+    let (verb, rest) = args.split_once(' ').unwrap_or((args, ""));
+    let rest = rest.trim();
+    let mut out = Vec::new();
+    let result: Result<(), String> = match (verb.to_ascii_lowercase().as_str(), rest) {
+        ("" | "list", "") => return list_lines(),
+        ("discover", "") => {
+            let found = discover(pump_ui);
+            out.push(alloc::format!("print: {} impresora(s) encontrada(s)", found));
+            out.extend(list_lines());
+            return out;
+        }
+        ("add", rest) if !rest.is_empty() => {
+            let (uri, name) = rest.split_once(' ').unwrap_or((rest, ""));
+            match redux_netparse::ipp::ipp_http_url(uri) {
+                Some(_) => {
+                    let name = if name.trim().is_empty() {
+                        redux_netparse::url::extract_url_host(uri).unwrap_or(uri)
+                    } else {
+                        name.trim()
+                    };
+                    add_printer(name, uri).map(|_| ()).map_err(String::from)
+                }
+                None => Err(String::from("URI invalida (ipp://host[:puerto]/ruta)")),
+            }
+            .map(|_| out.extend(list_lines()))
+        }
+        ("default", key) if !key.is_empty() => match lookup(key) {
+            Some(printer) => crate::config::set(DEFAULT_KEY, ConfigValue::Str(printer.name.clone()))
+                .map(|_| out.push(alloc::format!("print: predeterminada {}", printer.name)))
+                .map_err(String::from),
+            None => Err(String::from("impresora desconocida")),
+        },
+        ("info", key) => {
+            let names = printer_names();
+            let key = if key.is_empty() {
+                names.get(default_index(&names)).cloned().unwrap_or_default()
+            } else {
+                String::from(key)
+            };
+            match lookup(key.as_str()) {
+                Some(mut printer) => {
+                    // Always ask again: `info` is how to see the current state.
+                    printer.info = None;
+                    capabilities(&printer, pump_ui).map(|info| out.extend(info_lines(&printer, &info)))
+                }
+                None => Err(String::from("impresora desconocida")),
+            }
+        }
+        ("jobs", "") => {
+            refresh_jobs(pump_ui);
+            let jobs = jobs();
+            if jobs.is_empty() {
+                out.push(String::from("print: sin trabajos"));
+            }
+            for job in jobs.iter() {
+                out.push(alloc::format!(
+                    "  {:>3}  {:<12} {:<16} {}  {}",
+                    job.id,
+                    ipp_job_state_text(job.state),
+                    job.printer,
+                    job.format,
+                    job.title
+                ));
+            }
+            Ok(())
+        }
+        ("cancel", id) => match id.parse::<u32>() {
+            Ok(id) => cancel(id, pump_ui).map(|_| out.push(alloc::format!("print: trabajo {} cancelado", id))),
+            Err(_) => Err(String::from(USAGE)),
+        },
+        ("test", key) => {
+            let names = printer_names();
+            let key = if key.is_empty() {
+                names.get(default_index(&names)).cloned().unwrap_or_default()
+            } else {
+                String::from(key)
+            };
+            match lookup(key.as_str()) {
+                Some(printer) => print_document(printer.name.as_str(), &test_page(&printer), 1, pump_ui)
+                    .map(|id| out.push(alloc::format!("print: trabajo {} enviado a {}", id, printer.name))),
+                None => Err(String::from("impresora desconocida")),
+            }
+        }
+        _ => Err(String::from(USAGE)),
+    };
+    if let Err(e) = result {
+        out.push(alloc::format!("print: {}", e));
+    }
+    out
+}
+
+const USAGE: &str = "uso: print [list|discover|add <uri> [nombre]|default <n>|info [n]|jobs|cancel <id>|test [n]]";
+
+crate::selftest::kernel_tests! {
+    "print";
+
+    fn paginate_wraps_and_breaks_pages() {
+        let long: String = core::iter::repeat('x').take(COLUMNS + 5).collect();
+        let text = alloc::format!("a\tb\n{}\n\x0cfin\n", long);
+        let pages = paginate(text.as_str(), 3);
+        crate::selftest::ensure_eq(pages.len(), 2, "salto de pagina")?;
+        crate::selftest::ensure_eq(pages[0][0].as_str(), "a   b", "tabulador")?;
+        crate::selftest::ensure_eq(pages[0][1].len(), COLUMNS, "linea cortada")?;
+        crate::selftest::ensure_eq(pages[0][2].as_str(), "xxxxx", "resto")?;
+        crate::selftest::ensure_eq(pages[1][0].as_str(), "fin", "pagina nueva")?;
+        crate::selftest::ensure_eq(paginate("", 61).len(), 1, "vacio")?;
+        crate::selftest::ensure_eq(MEDIA_A4.lines_per_page(), 61, "lineas A4")
+    }
+
+    fn pdf_xref_points_at_objects() {
+        let pages = paginate("Hola (mundo)\nnino \u{f1}", 61);
+        let pdf = text_pdf("Nota", &pages, MEDIA_A4);
+        let text = String::from_utf8_lossy(&pdf).into_owned();
+        crate::selftest::ensure(text.contains("(Hola \\(mundo\\)) Tj"), "escape")?;
+        crate::selftest::ensure(text.contains("\\361"), "latin-1")?;
+        let start = text.rfind("startxref\n").ok_or("sin startxref")? + 10;
+        let xref: usize = text[start..].lines().next().unwrap_or("").parse().map_err(|_| "startxref")?;
+        crate::selftest::ensure(pdf[xref..].starts_with(b"xref\n0 7\n"), "tabla xref")?;
+        let table = String::from_utf8_lossy(&pdf[xref..]).into_owned();
+        for (i, entry) in table.lines().skip(3).take(6).enumerate() {
+            let offset: usize = entry[..10].parse().map_err(|_| "entrada")?;
+            let header = alloc::format!("{} 0 obj", i + 1);
+            crate::selftest::ensure(pdf[offset..].starts_with(header.as_bytes()), "objeto")?;
+        }
+        Ok(())
+    }
+
+    fn raster_text_and_format_choice() {
+        let mut info = PrinterInfo::default();
+        info.formats = alloc::vec![String::from("image/pwg-raster")];
+        info.resolutions = alloc::vec![300, 600];
+        info.raster_types = alloc::vec![String::from("sgray_8")];
+        crate::selftest::ensure_eq(raster_dpi(&info), 300, "menor resolucion")?;
+        let text = Document::Text { title: String::from("t"), text: String::from("HI") };
+        let (format, data) = render(&text, &info)?;
+        crate::selftest::ensure_eq(format, "image/pwg-raster", "raster sin pdf")?;
+        let (page, pixels, _) = redux_netparse::pwg::decode_pwg_page(&data[4..]).ok_or("pagina")?;
+        crate::selftest::ensure_eq((page.width, page.height, page.color), (2479, 3508, false), "A4 300 dpi")?;
+        // Top-left pixel of 'H' at 4x: the margin is 208 px on both axes.
+        crate::selftest::ensure_eq(pixels[208 * 2479 + 208], 0, "tinta")?;
+        crate::selftest::ensure_eq(pixels[0], 0xFF, "margen")?;
+
+        info.formats.push(String::from("application/pdf"));
+        info.resolutions.clear();
+        crate::selftest::ensure_eq(raster_dpi(&info), FALLBACK_DPI, "sin resoluciones")?;
+        crate::selftest::ensure_eq(render(&text, &info)?.0, "application/pdf", "texto en pdf")?;
+        let image = Document::Image { title: String::from("i"), width: 2, height: 1, pixels: alloc::vec![0xFF0000, 0x0000FF] };
+        crate::selftest::ensure_eq(render(&image, &info)?.0, "image/pwg-raster", "imagen en raster")?;
+        info.formats = alloc::vec![String::from("image/urf")];
+        crate::selftest::ensure(render(&text, &info).is_err(), "formato no soportado")
+    }
+
+    fn discovered_services_become_ipp_uris() {
+        let service = MdnsService {
+            name: String::from("Oficina"),
+            instance: String::from("Oficina._ipp._tcp.local"),
+            host: String::from("print.local"),
+            addr: [192, 168, 1, 40],
+            port: 631,
+            txt: alloc::vec![String::from("rp=/ipp/print2")],
+        };
+        crate::selftest::ensure_eq(service_uri(&service).as_str(), "ipp://192.168.1.40:631/ipp/print2", "uri")
+    }
+}
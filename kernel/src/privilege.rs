@@ -1,5 +1,6 @@
 use core::arch::global_asm;
 
+use crate::stack_guard::GuardedStack;
 use crate::{hal, interrupts, syscall};
 
 const IA32_EFER: u32 = 0xC000_0080;
@@ -94,7 +95,7 @@ struct Stack<const N: usize>([u8; N]);
 
 static mut GDT: [u64; GDT_LEN] = [0; GDT_LEN];
 static mut TSS: Tss64 = Tss64::new();
-static mut KERNEL_STACK: Stack<KSTACK_SIZE> = Stack([0; KSTACK_SIZE]);
+static mut KERNEL_STACK: GuardedStack<KSTACK_SIZE> = GuardedStack::new();
 static mut USER_STACK: Stack<USTACK_SIZE> = Stack([0; USTACK_SIZE]);
 
 static mut PHASE: u8 = PHASE_OFF;
@@ -574,7 +575,9 @@ extern "C" fn syscall_hw_dispatch(id: u64, a0: u64, a1: u64, a2: u64, a3: u64) -
     let (a4, a5) = unsafe { (SYSCALL_ARG4, SYSCALL_ARG5) };
     if unsafe { LINUX_REAL_SLICE_ACTIVE != 0 } && syscall::linux_shim_active() {
         // While Linux runreal shim is active, route raw CPU SYSCALL numbers to Linux ABI shim.
+        let guest = crate::uaccess::GuestScope::enter(syscall::linux_guest_regions());
         let result = syscall::linux_shim_invoke(id, a0, a1, a2, a3, a4, a5) as u64;
+        drop(guest);
         unsafe {
             if LINUX_REAL_SLICE_ACTIVE != 0 && !syscall::linux_shim_active() {
                 // Process exited: force return to kernel without keeping resumable context.
@@ -633,8 +636,7 @@ fn enable_user_fpu_sse() {
 fn phase1_prepare_gdt_tss() {
     enable_user_fpu_sse();
     unsafe {
-        let kstack_base = (core::ptr::addr_of!(KERNEL_STACK.0) as *const u8) as u64;
-        let kstack_top = kstack_base + KSTACK_SIZE as u64;
+        let kstack_top = (*core::ptr::addr_of!(KERNEL_STACK)).top();
 
         SYSCALL_KERNEL_STACK_TOP = kstack_top;
         CPL3_TEST_USER_RSP = user_stack_top();
@@ -1021,10 +1023,47 @@ pub fn init_privilege_layers_uefi_lite() {
         UEFI_INIT_STEP = 1;
         enable_user_fpu_sse();
 
+        extend_uefi_gdt_and_load_tss();
+
+        PHASE = PHASE_GDT_TSS;
+
+        // Install user interrupt gates + reload IDT
+        UEFI_INIT_STEP = 9;
+        phase2_install_user_gates();
+        PHASE = PHASE_USER_GATES;
+
+        // Re-enable interrupts
+        UEFI_INIT_STEP = 10;
+        core::arch::asm!("sti", options(nostack, preserves_flags));
+    }
+}
+
+/// Give the TSS the double-fault stack (IST 1) and load it now, before the
+/// rest of the privilege layers, so a kernel stack overflow lands on a good
+/// stack. Interrupts end as they were.
+pub fn load_task_register_uefi(double_fault_stack_top: u64) {
+    unsafe {
+        TSS.ist[0] = double_fault_stack_top;
+        let rflags: u64;
+        core::arch::asm!("pushfq", "pop {}", out(reg) rflags, options(preserves_flags));
+        extend_uefi_gdt_and_load_tss();
+        if rflags & (1 << 9) != 0 {
+            core::arch::asm!("sti", options(nostack, preserves_flags));
+        }
+    }
+}
+
+pub fn register_stack_guard() {
+    let base = unsafe { (*core::ptr::addr_of!(KERNEL_STACK)).guard_base() };
+    crate::stack_guard::register(base, crate::stack_guard::Owner::RingTransition);
+}
+
+/// Steps 2-8 of the UEFI-safe init. Leaves interrupts disabled.
+fn extend_uefi_gdt_and_load_tss() {
+    unsafe {
         // Set up kernel stack for ring transitions
         UEFI_INIT_STEP = 2;
-        let kstack_base = (core::ptr::addr_of!(KERNEL_STACK.0) as *const u8) as u64;
-        let kstack_top = kstack_base + KSTACK_SIZE as u64;
+        let kstack_top = (*core::ptr::addr_of!(KERNEL_STACK)).top();
         SYSCALL_KERNEL_STACK_TOP = kstack_top;
         CPL3_TEST_USER_RSP = user_stack_top();
 
@@ -1091,17 +1130,6 @@ pub fn init_privilege_layers_uefi_lite() {
             "ltr ax",
             options(nostack)
         );
-
-        PHASE = PHASE_GDT_TSS;
-
-        // Install user interrupt gates + reload IDT
-        UEFI_INIT_STEP = 9;
-        phase2_install_user_gates();
-        PHASE = PHASE_USER_GATES;
-
-        // Re-enable interrupts
-        UEFI_INIT_STEP = 10;
-        core::arch::asm!("sti", options(nostack, preserves_flags));
     }
 }
 
@@ -0,0 +1,426 @@
+//! Process environment for programs started from the shell or the launcher.
+//!
+//! Each program gets a working directory, a binding for stdin, stdout and
+//! stderr, and an exit state. The Linux shim asks the foreground entry for
+//! `getcwd`/`chdir` and sends fd 0-2 through it; the desktop turns the
+//! queued [`Event`]s into terminal output and, for a non-zero exit status,
+//! a notification.
+//!
+//! A console app launched from a terminal (or from the Start menu, which
+//! opens one) has all three streams bound to that window: its output is
+//! appended there and lines typed in it go to the app's stdin until it
+//! exits.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::spinlock::SpinLock;
+
+pub type ProcId = u32;
+
+/// Finished entries kept for `jobs` once the table is full.
+const MAX_ENTRIES: usize = 32;
+const STDIN_MAX_BYTES: usize = 4096;
+/// Output lines waiting for the desktop; the oldest are dropped past this.
+const MAX_PENDING_EVENTS: usize = 512;
+const NAME_MAX_BYTES: usize = 48;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stream {
+    Null,
+    /// A terminal window, by window id.
+    Terminal(usize),
+    Klog,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Stdio {
+    pub stdin: Stream,
+    pub stdout: Stream,
+    pub stderr: Stream,
+}
+
+impl Stdio {
+    /// All three streams on one terminal window.
+    pub const fn terminal(win_id: usize) -> Self {
+        Self {
+            stdin: Stream::Terminal(win_id),
+            stdout: Stream::Terminal(win_id),
+            stderr: Stream::Terminal(win_id),
+        }
+    }
+
+    /// No input; output to the kernel log. For programs with no console.
+    pub const fn detached() -> Self {
+        Self {
+            stdin: Stream::Null,
+            stdout: Stream::Klog,
+            stderr: Stream::Klog,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum State {
+    Running,
+    Exited(i32),
+    /// Stopped from outside (user, or the launcher replacing it).
+    Killed,
+}
+
+#[derive(Clone, Debug)]
+pub struct ProcInfo {
+    pub id: ProcId,
+    pub name: String,
+    /// Working directory as the program sees it.
+    pub cwd: String,
+    pub stdio: Stdio,
+    pub state: State,
+    pub started_ms: u64,
+    pub ended_ms: u64,
+}
+
+#[derive(Clone, Debug)]
+pub enum Event {
+    Output {
+        win_id: usize,
+        line: String,
+    },
+    Exited {
+        id: ProcId,
+        name: String,
+        code: i32,
+        win_id: Option<usize>,
+    },
+}
+
+struct Entry {
+    info: ProcInfo,
+    stdin: VecDeque<u8>,
+}
+
+struct Table {
+    entries: Vec<Entry>,
+    next_id: ProcId,
+    foreground: Option<ProcId>,
+    events: VecDeque<Event>,
+    dropped_events: u64,
+}
+
+impl Table {
+    const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            next_id: 1,
+            foreground: None,
+            events: VecDeque::new(),
+            dropped_events: 0,
+        }
+    }
+
+    fn entry_mut(&mut self, id: ProcId) -> Option<&mut Entry> {
+        self.entries.iter_mut().find(|entry| entry.info.id == id)
+    }
+
+    fn foreground_mut(&mut self) -> Option<&mut Entry> {
+        let id = self.foreground?;
+        self.entry_mut(id)
+    }
+
+    fn push_event(&mut self, event: Event) {
+        if self.events.len() >= MAX_PENDING_EVENTS {
+            let _ = self.events.pop_front();
+            self.dropped_events = self.dropped_events.saturating_add(1);
+        }
+        self.events.push_back(event);
+    }
+
+    fn finish(&mut self, id: ProcId, state: State) -> bool {
+        let now = crate::timer::now_ms();
+        let Some(entry) = self.entry_mut(id) else {
+            return false;
+        };
+        if entry.info.state != State::Running {
+            return false;
+        }
+        entry.info.state = state;
+        entry.info.ended_ms = now;
+        entry.stdin.clear();
+        if self.foreground == Some(id) {
+            self.foreground = None;
+        }
+        true
+    }
+}
+
+static TABLE: SpinLock<Table> = SpinLock::new(Table::new());
+
+fn clip(text: &str, max: usize) -> String {
+    let mut end = text.len().min(max);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    String::from(&text[..end])
+}
+
+/// Normalise `path` to an absolute, `/`-separated path without `.`/`..`.
+pub fn normalize_path(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                let _ = parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    let mut out = String::from("/");
+    out.push_str(parts.join("/").as_str());
+    out
+}
+
+/// Register a program and make it the foreground one, whose streams the
+/// Linux shim uses.
+pub fn spawn(name: &str, cwd: &str, stdio: Stdio) -> ProcId {
+    let mut table = TABLE.lock();
+    if table.entries.len() >= MAX_ENTRIES {
+        if let Some(oldest) = table
+            .entries
+            .iter()
+            .position(|entry| entry.info.state != State::Running)
+        {
+            let _ = table.entries.remove(oldest);
+        }
+    }
+    let id = table.next_id;
+    table.next_id = table.next_id.wrapping_add(1).max(1);
+    table.entries.push(Entry {
+        info: ProcInfo {
+            id,
+            name: clip(name.trim(), NAME_MAX_BYTES),
+            cwd: normalize_path(cwd),
+            stdio,
+            state: State::Running,
+            started_ms: crate::timer::now_ms(),
+            ended_ms: 0,
+        },
+        stdin: VecDeque::new(),
+    });
+    table.foreground = Some(id);
+    id
+}
+
+pub fn foreground() -> Option<ProcId> {
+    TABLE.lock().foreground
+}
+
+/// Run `f` on the foreground program's working directory (`/` if none).
+pub fn with_cwd<R>(f: impl FnOnce(&str) -> R) -> R {
+    let mut table = TABLE.lock();
+    match table.foreground_mut() {
+        Some(entry) => f(entry.info.cwd.as_str()),
+        None => f("/"),
+    }
+}
+
+/// Change the foreground program's working directory. `cwd` must already
+/// be absolute; callers check that it exists.
+pub fn set_cwd(cwd: &str) -> bool {
+    let mut table = TABLE.lock();
+    match table.foreground_mut() {
+        Some(entry) => {
+            entry.info.cwd = normalize_path(cwd);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Send one line the foreground program wrote to `fd` (1 or 2) where that
+/// stream goes. False when there is no foreground program, so the caller
+/// can fall back to its own console.
+pub fn write_line(fd: u32, bytes: &[u8]) -> bool {
+    let mut table = TABLE.lock();
+    let Some(entry) = table.foreground_mut() else {
+        return false;
+    };
+    let stream = if fd == 2 {
+        entry.info.stdio.stderr
+    } else {
+        entry.info.stdio.stdout
+    };
+    let line = String::from_utf8_lossy(bytes).into_owned();
+    match stream {
+        Stream::Null => {}
+        Stream::Terminal(win_id) => table.push_event(Event::Output { win_id, line }),
+        Stream::Klog => {
+            let level = if fd == 2 {
+                crate::klog::Level::Warning
+            } else {
+                crate::klog::Level::Info
+            };
+            let text = alloc::format!("{}: {}", entry.info.name, line);
+            drop(table);
+            crate::klog::log(level, text.as_str());
+        }
+    }
+    true
+}
+
+/// Read queued input for the foreground program. `None` means stdin is not
+/// connected (end of file); `Some(0)` means nothing typed yet.
+pub fn read_stdin(out: &mut [u8]) -> Option<usize> {
+    let mut table = TABLE.lock();
+    let entry = table.foreground_mut()?;
+    if !matches!(entry.info.stdio.stdin, Stream::Terminal(_)) {
+        return None;
+    }
+    let n = out.len().min(entry.stdin.len());
+    for (slot, byte) in out.iter_mut().zip(entry.stdin.drain(..n)) {
+        *slot = byte;
+    }
+    Some(n)
+}
+
+/// Queue a line typed in terminal `win_id` for the running program reading
+/// from it. False when no program has that window as stdin, so the line is
+/// a shell command.
+pub fn push_stdin_line(win_id: usize, line: &str) -> bool {
+    let mut table = TABLE.lock();
+    let Some(entry) = table
+        .entries
+        .iter_mut()
+        .rev()
+        .find(|entry| entry.info.state == State::Running && entry.info.stdio.stdin == Stream::Terminal(win_id))
+    else {
+        return false;
+    };
+    let room = STDIN_MAX_BYTES.saturating_sub(entry.stdin.len());
+    if room > 0 {
+        entry.stdin.extend(line.bytes().take(room - 1));
+        entry.stdin.push_back(b'\n');
+    }
+    true
+}
+
+/// Record that `id` exited with `code`. Only the first exit counts.
+pub fn exit(id: ProcId, code: i32) {
+    let mut table = TABLE.lock();
+    if !table.finish(id, State::Exited(code)) {
+        return;
+    }
+    let Some(entry) = table.entry_mut(id) else {
+        return;
+    };
+    let name = entry.info.name.clone();
+    let win_id = match entry.info.stdio.stdout {
+        Stream::Terminal(win_id) => Some(win_id),
+        _ => None,
+    };
+    table.push_event(Event::Exited { id, name, code, win_id });
+}
+
+/// Record that `id` was stopped before it exited on its own.
+pub fn kill(id: ProcId) {
+    let _ = TABLE.lock().finish(id, State::Killed);
+}
+
+/// Output and exit events queued since the last call, oldest first.
+pub fn take_events() -> Vec<Event> {
+    let mut table = TABLE.lock();
+    table.events.drain(..).collect()
+}
+
+pub fn list() -> Vec<ProcInfo> {
+    TABLE.lock().entries.iter().map(|entry| entry.info.clone()).collect()
+}
+
+fn stream_label(stream: Stream) -> String {
+    match stream {
+        Stream::Null => String::from("null"),
+        Stream::Terminal(win_id) => alloc::format!("term#{}", win_id),
+        Stream::Klog => String::from("klog"),
+    }
+}
+
+/// `jobs`: programs started from the shell or launcher, newest last.
+pub fn command_lines(_args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let (foreground, dropped) = {
+        let table = TABLE.lock();
+        (table.foreground, table.dropped_events)
+    };
+    let entries = list();
+    if entries.is_empty() {
+        out.push(String::from("Jobs: ningun programa lanzado."));
+        return out;
+    }
+    out.push(alloc::format!(
+        "Jobs: {} programas (lineas descartadas={})",
+        entries.len(),
+        dropped
+    ));
+    for info in entries.iter() {
+        let state = match info.state {
+            State::Running => String::from("ejecutando"),
+            State::Exited(code) => alloc::format!("salida={}", code),
+            State::Killed => String::from("detenido"),
+        };
+        let elapsed = if info.state == State::Running {
+            crate::timer::now_ms().saturating_sub(info.started_ms)
+        } else {
+            info.ended_ms.saturating_sub(info.started_ms)
+        };
+        out.push(alloc::format!(
+            "  [{}]{} {} {} {}.{:03}s cwd={} in={} out={} err={}",
+            info.id,
+            if foreground == Some(info.id) { "*" } else { " " },
+            info.name,
+            state,
+            elapsed / 1000,
+            elapsed % 1000,
+            info.cwd,
+            stream_label(info.stdio.stdin),
+            stream_label(info.stdio.stdout),
+            stream_label(info.stdio.stderr)
+        ));
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "procenv";
+
+    fn paths_normalise() {
+        crate::selftest::ensure_eq(normalize_path("a/./b/../c/"), String::from("/a/c"), "relativa")?;
+        crate::selftest::ensure_eq(normalize_path("/../.."), String::from("/"), "raiz")
+    }
+
+    fn streams_and_exit_status() {
+        let id = spawn("selftest-app", "/tmp", Stdio::terminal(usize::MAX));
+        crate::selftest::ensure_eq(foreground(), Some(id), "primer plano")?;
+        crate::selftest::ensure(with_cwd(|cwd| cwd == "/tmp"), "cwd")?;
+        crate::selftest::ensure(push_stdin_line(usize::MAX, "hola"), "stdin conectado")?;
+        let mut buf = [0u8; 8];
+        crate::selftest::ensure_eq(read_stdin(&mut buf), Some(5), "lectura")?;
+        crate::selftest::ensure_eq(&buf[..5], &b"hola\n"[..], "linea")?;
+        crate::selftest::ensure(write_line(1, b"salida"), "stdout")?;
+        exit(id, 3);
+        exit(id, 0);
+        crate::selftest::ensure(!push_stdin_line(usize::MAX, "tarde"), "stdin cerrado")?;
+        let mut output = false;
+        let mut exits = Vec::new();
+        // Selftests run from an idle shell; anything else queued is dropped.
+        for event in take_events() {
+            match event {
+                Event::Output { win_id, line } if win_id == usize::MAX => output = line == "salida",
+                Event::Exited { id: exited, code, .. } if exited == id => exits.push(code),
+                _ => {}
+            }
+        }
+        crate::selftest::ensure(output, "evento de salida")?;
+        crate::selftest::ensure_eq(exits, alloc::vec![3], "una sola salida")
+    }
+}
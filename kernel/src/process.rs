@@ -1,5 +1,6 @@
 use crate::usermode;
 use crate::spinlock::SpinLock;
+use crate::stack_guard::GuardedStack;
 use core::arch::global_asm;
 use core::sync::atomic::{AtomicU32, Ordering};
 
@@ -11,6 +12,8 @@ const PRIORITY_LEVELS: usize = 4;
 const STARVATION_RELIEF_BASE_TICKS: u64 = 12;
 const CORE_BALANCE_INTERVAL_TICKS: u64 = 8;
 const KTHREAD_STACK_SIZE: usize = 16 * 1024;
+/// Kernel stack reserved per thread; the only per-process memory tracked.
+pub const THREAD_STACK_BYTES: usize = KTHREAD_STACK_SIZE;
 // Context switch asm path is kept in-tree but disabled by default until
 // IRQ-mode reentrancy is fully hardened on real hardware.
 const ENABLE_KTHREAD_CONTEXT_SWITCH: bool = true;
@@ -45,10 +48,6 @@ impl SwitchContext {
     }
 }
 
-#[repr(align(16))]
-#[derive(Clone, Copy)]
-struct KernelThreadStack([u8; KTHREAD_STACK_SIZE]);
-
 unsafe extern "C" {
     fn process_switch_context(prev: *mut SwitchContext, next: *const SwitchContext);
 }
@@ -81,7 +80,7 @@ process_switch_context:
 "#
 );
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[repr(u8)]
 pub enum RingLevel {
     Kernel = 0,
@@ -244,6 +243,42 @@ pub struct ThreadInfo {
     pub name_len: u8,
 }
 
+#[derive(Clone, Copy)]
+pub struct ProcessInfo {
+    pub pid: u16,
+    pub ring: RingLevel,
+    pub active: bool,
+    pub name: [u8; NAME_MAX],
+    pub name_len: u8,
+    /// Threads not yet dead.
+    pub threads: u8,
+    /// Dispatches of all its threads since boot.
+    pub runs: u64,
+}
+
+impl ProcessInfo {
+    pub fn name_str(&self) -> &str {
+        let len = (self.name_len as usize).min(NAME_MAX);
+        core::str::from_utf8(&self.name[..len]).unwrap_or("?")
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KillError {
+    NotFound,
+    /// The requester runs at a less privileged ring than the process.
+    Privilege,
+}
+
+impl KillError {
+    pub const fn message(self) -> &'static str {
+        match self {
+            KillError::NotFound => "proceso inexistente",
+            KillError::Privilege => "privilegio insuficiente",
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 struct RunQueue {
     entries: [u8; MAX_THREADS],
@@ -495,10 +530,8 @@ impl ProcessManager {
 
     fn thread_stack_bounds(thread_index: usize) -> (u64, u64) {
         unsafe {
-            let slot = core::ptr::addr_of_mut!(THREAD_STACKS[thread_index]);
-            let base = slot as u64;
-            let top = base.saturating_add(KTHREAD_STACK_SIZE as u64);
-            (base, top)
+            let slot = &*core::ptr::addr_of!(THREAD_STACKS[thread_index]);
+            (slot.bottom(), slot.top())
         }
     }
 
@@ -1077,6 +1110,55 @@ impl ProcessManager {
         self.thread_count
     }
 
+    fn process_info(&self, index: usize) -> Option<ProcessInfo> {
+        if index >= self.process_count {
+            return None;
+        }
+        let p = self.processes[index];
+        let mut threads = 0u8;
+        let mut runs = 0u64;
+        let mut i = 0usize;
+        while i < self.thread_count {
+            let t = &self.threads[i];
+            if t.pid == p.pid {
+                runs = runs.saturating_add(t.runs);
+                if t.active && t.state != ThreadState::Dead {
+                    threads += 1;
+                }
+            }
+            i += 1;
+        }
+        Some(ProcessInfo {
+            pid: p.pid,
+            ring: p.ring,
+            active: p.active,
+            name: p.name,
+            name_len: p.name_len,
+            threads,
+            runs,
+        })
+    }
+
+    /// Mark `pid` and its threads dead. A running thread is descheduled by
+    /// `on_tick_finish`; queued ones are skipped when popped.
+    fn kill(&mut self, pid: u16, requester: RingLevel) -> Result<(), KillError> {
+        let index = (0..self.process_count)
+            .find(|&i| self.processes[i].active && self.processes[i].pid == pid)
+            .ok_or(KillError::NotFound)?;
+        if (requester as u8) > (self.processes[index].ring as u8) {
+            return Err(KillError::Privilege);
+        }
+        self.processes[index].active = false;
+        let mut i = 0usize;
+        while i < self.thread_count {
+            if self.threads[i].pid == pid {
+                self.threads[i].state = ThreadState::Dead;
+            }
+            i += 1;
+        }
+        Ok(())
+    }
+
     fn dispatches(&self) -> u64 {
         let mut total = 0u64;
         let mut i = 0usize;
@@ -1185,8 +1267,10 @@ impl ProcessManager {
 static PM_LOCK: SpinLock<()> = SpinLock::new(());
 static mut PM: ProcessManager = ProcessManager::new();
 static IRQ_PREEMPT_HINTS: AtomicU32 = AtomicU32::new(0);
-static mut THREAD_STACKS: [KernelThreadStack; MAX_THREADS] =
-    [KernelThreadStack([0; KTHREAD_STACK_SIZE]); MAX_THREADS];
+static mut THREAD_STACKS: [GuardedStack<KTHREAD_STACK_SIZE>; MAX_THREADS] = {
+    const INIT: GuardedStack<KTHREAD_STACK_SIZE> = GuardedStack::new();
+    [INIT; MAX_THREADS]
+};
 static mut PROCESS_ACTIVE_THREAD_INDEX: [usize; MAX_CORES] = [usize::MAX; MAX_CORES];
 static mut PROCESS_ACTIVE_TICK: [u64; MAX_CORES] = [0; MAX_CORES];
 static mut KERNEL_PREEMPT_RESUME_RIP: [u64; MAX_CORES] = [0; MAX_CORES];
@@ -1275,11 +1359,37 @@ pub fn thread_info(index: usize) -> Option<ThreadInfo> {
     unsafe { PM.thread_info(index) }
 }
 
+/// `thread_info` for the fault handler, which may have interrupted the
+/// holder of `PM_LOCK`: reads without it when it is taken.
+pub fn thread_info_for_fault(index: usize) -> Option<ThreadInfo> {
+    let _guard = PM_LOCK.try_lock();
+    unsafe { PM.thread_info(index) }
+}
+
+pub fn register_stack_guards() {
+    for index in 0..MAX_THREADS {
+        let base = unsafe { (*core::ptr::addr_of!(THREAD_STACKS[index])).guard_base() };
+        crate::stack_guard::register(base, crate::stack_guard::Owner::Thread(index));
+    }
+}
+
 pub fn thread_count() -> usize {
     let _guard = PM_LOCK.lock();
     unsafe { PM.thread_count() }
 }
 
+pub fn process_info(index: usize) -> Option<ProcessInfo> {
+    let _guard = PM_LOCK.lock();
+    unsafe { PM.process_info(index) }
+}
+
+/// Terminate `pid` on behalf of code running at `requester`; a user-ring
+/// requester cannot end kernel-ring processes.
+pub fn kill(pid: u16, requester: RingLevel) -> Result<(), KillError> {
+    let _guard = PM_LOCK.lock();
+    unsafe { PM.kill(pid, requester) }
+}
+
 pub fn dispatches() -> u64 {
     let _guard = PM_LOCK.lock();
     unsafe { PM.dispatches() }
@@ -1396,3 +1506,23 @@ extern "C" fn process_irq_preempt_resume_rip() -> u64 {
         rip
     }
 }
+
+crate::selftest::kernel_tests! {
+    "process";
+
+    fn kill_respects_ring_and_marks_threads_dead() {
+        fn noop(_tid: usize, _tick: u64) {}
+        let mut pm = ProcessManager::new();
+        let kernel = pm.add_process("kernel.svc", RingLevel::Kernel).ok_or("kernel")?;
+        let user = pm.add_process("app", RingLevel::User).ok_or("app")?;
+        pm.add_thread(user, "app.main", RingLevel::User, ThreadPriority::Normal, noop).ok_or("hilo")?;
+        pm.add_thread(user, "app.io", RingLevel::User, ThreadPriority::Background, noop).ok_or("hilo")?;
+        crate::selftest::ensure_eq(pm.process_info(1).map(|p| p.threads), Some(2), "hilos vivos")?;
+        crate::selftest::ensure_eq(pm.kill(kernel, RingLevel::User), Err(KillError::Privilege), "anillo 0")?;
+        crate::selftest::ensure_eq(pm.kill(user, RingLevel::User), Ok(()), "anillo 3")?;
+        crate::selftest::ensure_eq(pm.kill(user, RingLevel::User), Err(KillError::NotFound), "ya terminado")?;
+        let info = pm.process_info(1).ok_or("info")?;
+        crate::selftest::ensure(!info.active && info.threads == 0, "hilos muertos")?;
+        crate::selftest::ensure_eq(pm.kill(kernel, RingLevel::Kernel), Ok(()), "el kernel puede")
+    }
+}
@@ -1,4 +1,5 @@
 use crate::println;
+use crate::spinlock::SpinLock;
 
 // Simple fixed-size array to mock a HashMap for no_std
 #[derive(Clone, Copy)]
@@ -28,23 +29,39 @@ impl QuotaManager {
     }
 
     pub fn set_limit(&mut self, app_id: &str, limit: u64) {
+        if !self.insert(app_id, limit) {
+            println("QuotaManager: Table full!");
+        }
+    }
+
+    /// `set_limit` without the log line, for callers holding a lock; false
+    /// when the table is full.
+    fn insert(&mut self, app_id: &str, limit: u64) -> bool {
         let h = Self::hash(app_id);
         for entry in self.entries.iter_mut() {
             if entry.app_id_hash == 0 || entry.app_id_hash == h {
                 entry.app_id_hash = h;
                 entry.limit = limit;
-                return;
+                return true;
             }
         }
-        println("QuotaManager: Table full!");
+        false
     }
 
     pub fn check_write(&mut self, app_id: &str, size: u64) -> bool {
+        let allowed = self.charge(app_id, size);
+        if !allowed {
+            println("Quota Exceeded for App!");
+        }
+        allowed
+    }
+
+    /// `check_write` without the log line, for callers holding a lock.
+    fn charge(&mut self, app_id: &str, size: u64) -> bool {
         let h = Self::hash(app_id);
         for entry in self.entries.iter_mut() {
             if entry.app_id_hash == h {
                 if entry.usage + size > entry.limit {
-                    println("Quota Exceeded for App!");
                     return false;
                 }
                 entry.usage += size;
@@ -57,6 +74,11 @@ impl QuotaManager {
         true
     }
     
+    pub fn has_entry(&self, app_id: &str) -> bool {
+        let h = Self::hash(app_id);
+        self.entries.iter().any(|entry| entry.app_id_hash == h)
+    }
+
     pub fn get_usage(&self, app_id: &str) -> u64 {
         let h = Self::hash(app_id);
         for entry in self.entries.iter() {
@@ -70,6 +92,30 @@ impl QuotaManager {
 
 static mut GLOBAL_QUOTA: QuotaManager = QuotaManager::new();
 
+// Bytes downloaded through the userspace HTTP API (redux_http), per caller.
+const NET_QUOTA_DEFAULT_BYTES: u64 = 1024 * 1024 * 32;
+static GLOBAL_NET_QUOTA: SpinLock<QuotaManager> = SpinLock::new(QuotaManager::new());
+
+/// Charge `bytes` of network download to `app_id`; false when over quota.
+pub fn charge_net(app_id: &str, bytes: u64) -> bool {
+    let (full, allowed) = {
+        let mut quota = GLOBAL_NET_QUOTA.lock();
+        let full = !quota.has_entry(app_id) && !quota.insert(app_id, NET_QUOTA_DEFAULT_BYTES);
+        (full, quota.charge(app_id, bytes))
+    };
+    if full {
+        println("QuotaManager: Table full!");
+    }
+    if !allowed {
+        println("Quota Exceeded for App!");
+    }
+    allowed
+}
+
+pub fn net_usage(app_id: &str) -> u64 {
+    GLOBAL_NET_QUOTA.lock().get_usage(app_id)
+}
+
 pub fn init() {
     println("QuotaManager: Initialized.");
     unsafe {
@@ -0,0 +1,407 @@
+//! Kernel random numbers.
+//!
+//! Everything gathered goes into a 32-byte pool through SHA-256. The pool
+//! keys a ChaCha20 generator that rekeys itself from its own keystream after
+//! every request, so a captured state does not reveal earlier output.
+//!
+//! At boot the pool gets RDSEED (RDRAND when RDSEED is missing), TSC jitter
+//! over a memory walk and the firmware clock; `tpm` adds the TPM's RNG. Later,
+//! the timing of every keyboard and mouse event is added, and each request
+//! mixes in fresh RDRAND. The pool is folded into the key once it has
+//! collected `RESEED_BITS` new bits. Only RDSEED/RDRAND, jitter, the TPM and
+//! input count towards the estimate.
+//!
+//! `getrandom` draws from here, and through it rustls' `OsRng` (TLS client
+//! randoms and key shares). So do the Linux `getrandom` syscall, the native
+//! `SYS_GET_RANDOM`, `AT_RANDOM`, smoltcp's seed (TCP initial sequence
+//! numbers, DHCP transaction IDs), the first ephemeral port and `aslr`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::arch::asm;
+use core::arch::x86_64::{__cpuid, __cpuid_count};
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+
+use crate::crypto::Sha256;
+use crate::println;
+use crate::spinlock::SpinLock;
+
+/// Bits the pool must collect before it is folded into the key.
+const RESEED_BITS: u32 = 128;
+/// Credited bits below which the generator reports itself as weak.
+const SEEDED_BITS: u32 = 128;
+/// Jitter samples taken at boot; one bit is credited per eight.
+const JITTER_SAMPLES: usize = 1024;
+/// Largest request the `random` command prints.
+const HEX_MAX: usize = 64;
+const ARCH_RETRIES: usize = 10;
+
+static RDRAND: AtomicBool = AtomicBool::new(false);
+static RDSEED: AtomicBool = AtomicBool::new(false);
+static INPUT_EVENTS: AtomicU64 = AtomicU64::new(0);
+static STATE: SpinLock<Option<Generator>> = SpinLock::new(None);
+
+struct Generator {
+    pool: [u8; 32],
+    key: [u8; 32],
+    /// Bits credited since boot, saturating.
+    entropy_bits: u32,
+    /// Bits in the pool not yet folded into the key.
+    pending_bits: u32,
+    reseeds: u64,
+    bytes_out: u64,
+}
+
+impl Generator {
+    fn new() -> Self {
+        Self {
+            pool: [0; 32],
+            key: [0; 32],
+            entropy_bits: 0,
+            pending_bits: 0,
+            reseeds: 0,
+            bytes_out: 0,
+        }
+    }
+
+    /// pool = SHA-256(pool || tag || data)
+    fn mix(&mut self, tag: &[u8], data: &[u8], bits: u32) {
+        let mut hash = Sha256::new();
+        hash.update(&self.pool);
+        hash.update(tag);
+        hash.update(data);
+        self.pool = hash.finish();
+        self.entropy_bits = self.entropy_bits.saturating_add(bits);
+        self.pending_bits = self.pending_bits.saturating_add(bits);
+    }
+
+    /// key = SHA-256(key || pool)
+    fn reseed(&mut self) {
+        let mut hash = Sha256::new();
+        hash.update(&self.key);
+        hash.update(&self.pool);
+        self.key = hash.finish();
+        self.pending_bits = 0;
+        self.reseeds += 1;
+    }
+
+    fn fill(&mut self, out: &mut [u8]) {
+        let mut fresh = [0u8; 32];
+        if arch_fill(&mut fresh, false) {
+            for (k, f) in self.key.iter_mut().zip(fresh.iter()) {
+                *k ^= f;
+            }
+        }
+        if self.pending_bits >= RESEED_BITS {
+            self.reseed();
+        }
+        let mut cipher = ChaCha20::new(&self.key.into(), &[0u8; 12].into());
+        // The first 32 bytes become the next key; the caller never sees them.
+        let mut next_key = [0u8; 32];
+        cipher.apply_keystream(&mut next_key);
+        out.fill(0);
+        cipher.apply_keystream(out);
+        self.key = next_key;
+        self.bytes_out = self.bytes_out.saturating_add(out.len() as u64);
+    }
+}
+
+fn rdrand64() -> Option<u64> {
+    for _ in 0..ARCH_RETRIES {
+        let value: u64;
+        let ok: u8;
+        unsafe {
+            asm!("rdrand {v}", "setc {ok}", v = out(reg) value, ok = out(reg_byte) ok, options(nomem, nostack));
+        }
+        // Some AMD parts return all ones with CF set after resume.
+        if ok != 0 && value != 0 && value != u64::MAX {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn rdseed64() -> Option<u64> {
+    for _ in 0..ARCH_RETRIES {
+        let value: u64;
+        let ok: u8;
+        unsafe {
+            asm!("rdseed {v}", "setc {ok}", v = out(reg) value, ok = out(reg_byte) ok, options(nomem, nostack));
+        }
+        if ok != 0 && value != 0 && value != u64::MAX {
+            return Some(value);
+        }
+        core::hint::spin_loop();
+    }
+    None
+}
+
+/// Fill `out` from RDSEED (`seed`) or RDRAND if that instruction is
+/// present; false when it is missing or stops answering.
+fn arch_fill(out: &mut [u8], seed: bool) -> bool {
+    let (present, source): (_, fn() -> Option<u64>) = if seed {
+        (RDSEED.load(Ordering::Relaxed), rdseed64)
+    } else {
+        (RDRAND.load(Ordering::Relaxed), rdrand64)
+    };
+    if !present {
+        return false;
+    }
+    for chunk in out.chunks_mut(8) {
+        let Some(value) = source() else {
+            return false;
+        };
+        chunk.copy_from_slice(&value.to_le_bytes()[..chunk.len()]);
+    }
+    true
+}
+
+fn detect() {
+    let leaf1 = unsafe { __cpuid(1) };
+    let max_leaf = unsafe { __cpuid(0) }.eax;
+    let leaf7_ebx = if max_leaf >= 7 {
+        unsafe { __cpuid_count(7, 0) }.ebx
+    } else {
+        0
+    };
+    RDRAND.store(leaf1.ecx & (1 << 30) != 0, Ordering::Relaxed);
+    RDSEED.store(leaf7_ebx & (1 << 18) != 0, Ordering::Relaxed);
+    // A stuck generator repeats itself: stop trusting it.
+    if RDRAND.load(Ordering::Relaxed) && rdrand64() == rdrand64() {
+        RDRAND.store(false, Ordering::Relaxed);
+    }
+    if RDSEED.load(Ordering::Relaxed) && rdseed64() == rdseed64() {
+        RDSEED.store(false, Ordering::Relaxed);
+    }
+}
+
+/// TSC deltas across a short memory walk: cache and bus timing vary from run
+/// to run even on an idle machine.
+fn jitter_samples(out: &mut [u8; JITTER_SAMPLES]) {
+    let mut scratch = [0u8; 256];
+    let mut index = 0usize;
+    let mut last = crate::hal::rdtsc();
+    for slot in out.iter_mut() {
+        for _ in 0..16 {
+            index = (index + scratch[index] as usize + 97) % scratch.len();
+            scratch[index] = scratch[index].wrapping_add(index as u8);
+        }
+        let now = crate::hal::rdtsc();
+        *slot = now.wrapping_sub(last) as u8;
+        last = now;
+    }
+    core::hint::black_box(&scratch);
+}
+
+fn with_generator<T>(run: impl FnOnce(&mut Generator) -> T) -> T {
+    let mut state = STATE.lock();
+    let generator = state.get_or_insert_with(|| {
+        let mut generator = Generator::new();
+        seed(&mut generator);
+        generator
+    });
+    run(generator)
+}
+
+fn seed(generator: &mut Generator) {
+    detect();
+    let mut arch = [0u8; 64];
+    if arch_fill(&mut arch, true) {
+        generator.mix(b"rdseed", &arch, 256);
+    } else if arch_fill(&mut arch, false) {
+        generator.mix(b"rdrand", &arch, 256);
+    }
+    let mut jitter = [0u8; JITTER_SAMPLES];
+    jitter_samples(&mut jitter);
+    generator.mix(b"jitter", &jitter, (JITTER_SAMPLES / 8) as u32);
+    let mut clock = [0u8; 24];
+    clock[..8].copy_from_slice(&crate::hal::rdtsc().to_le_bytes());
+    clock[8..16].copy_from_slice(&crate::perf::now_us().to_le_bytes());
+    if let Ok(time) = uefi::runtime::get_time() {
+        clock[16] = time.second();
+        clock[17] = time.minute();
+        clock[18] = time.hour();
+        clock[19] = time.day();
+        clock[20] = time.month();
+        clock[21..23].copy_from_slice(&time.year().to_le_bytes());
+    }
+    generator.mix(b"clock", &clock, 0);
+    generator.reseed();
+}
+
+/// Seed the pool and report the sources found.
+pub fn init() {
+    let strong = with_generator(|generator| generator.entropy_bits >= SEEDED_BITS);
+    println(
+        alloc::format!(
+            "Random: rdseed {}, rdrand {}, semilla {}",
+            yes_no(RDSEED.load(Ordering::Relaxed)),
+            yes_no(RDRAND.load(Ordering::Relaxed)),
+            if strong {
+                "completa"
+            } else {
+                "debil, se completa con la entrada"
+            }
+        )
+        .as_str(),
+    );
+}
+
+/// Fill `out` with random bytes. Seeds on first use, so it works before
+/// `init`.
+pub fn fill(out: &mut [u8]) {
+    with_generator(|generator| generator.fill(out));
+}
+
+pub fn next_u64() -> u64 {
+    let mut bytes = [0u8; 8];
+    fill(&mut bytes);
+    u64::from_le_bytes(bytes)
+}
+
+pub fn next_u32() -> u32 {
+    let mut bytes = [0u8; 4];
+    fill(&mut bytes);
+    u32::from_le_bytes(bytes)
+}
+
+/// Mix `data` into the pool, crediting `bits` of entropy.
+pub fn add_entropy(data: &[u8], bits: u32) {
+    with_generator(|generator| generator.mix(b"extra", data, bits));
+}
+
+/// Called for every keyboard and mouse event: the TSC at arrival plus
+/// `value`, credited one bit.
+pub fn add_input_timing(value: u64) {
+    let Some(mut state) = STATE.try_lock() else {
+        return;
+    };
+    let Some(generator) = state.as_mut() else {
+        return;
+    };
+    INPUT_EVENTS.fetch_add(1, Ordering::Relaxed);
+    let mut sample = [0u8; 16];
+    sample[..8].copy_from_slice(&crate::hal::rdtsc().to_le_bytes());
+    sample[8..].copy_from_slice(&value.to_le_bytes());
+    generator.mix(b"input", &sample, 1);
+}
+
+/// At least `SEEDED_BITS` bits credited.
+pub fn seeded() -> bool {
+    STATE
+        .lock()
+        .as_ref()
+        .map(|generator| generator.entropy_bits >= SEEDED_BITS)
+        .unwrap_or(false)
+}
+
+fn getrandom_source(out: &mut [u8]) -> Result<(), getrandom::Error> {
+    fill(out);
+    Ok(())
+}
+
+getrandom::register_custom_getrandom!(getrandom_source);
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "si"
+    } else {
+        "no"
+    }
+}
+
+pub fn status_lines() -> Vec<String> {
+    let (bits, reseeds, bytes_out) =
+        with_generator(|generator| (generator.entropy_bits, generator.reseeds, generator.bytes_out));
+    alloc::vec![
+        alloc::format!(
+            "Fuentes: rdseed {}, rdrand {}, jitter TSC si, eventos de entrada {}",
+            yes_no(RDSEED.load(Ordering::Relaxed)),
+            yes_no(RDRAND.load(Ordering::Relaxed)),
+            INPUT_EVENTS.load(Ordering::Relaxed)
+        ),
+        alloc::format!(
+            "Entropia estimada {} bits ({}), resembrados {}, bytes entregados {}",
+            bits,
+            if bits >= SEEDED_BITS { "completa" } else { "debil" },
+            reseeds,
+            crate::perf::format_bytes(bytes_out)
+        ),
+    ]
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes.iter() {
+        let _ = write!(out, "{:02x}", byte);
+    }
+    out
+}
+
+/// `random [status]` shows the sources and estimate; `random <n>` prints `n`
+/// random bytes in hex.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim();
+    if args.is_empty() || args == "status" {
+        return status_lines();
+    }
+    match args.parse::<usize>() {
+        Ok(len) if (1..=HEX_MAX).contains(&len) => {
+            let mut bytes = alloc::vec![0u8; len];
+            fill(&mut bytes);
+            alloc::vec![hex(&bytes)]
+        }
+        _ => alloc::vec![alloc::format!("Uso: random [status|<bytes 1-{}>]", HEX_MAX)],
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "random";
+
+    fn outputs_differ() {
+        let mut first = [0u8; 32];
+        let mut second = [0u8; 32];
+        fill(&mut first);
+        fill(&mut second);
+        crate::selftest::ensure(first != second, "dos lecturas iguales")?;
+        crate::selftest::ensure(first != [0u8; 32], "salida a cero")?;
+        Ok(())
+    }
+
+    fn generator_rekeys() {
+        let mut generator = Generator::new();
+        generator.key = [9; 32];
+        let mut first = [0u8; 16];
+        generator.fill(&mut first);
+        crate::selftest::ensure(generator.key != [9; 32], "clave sin cambiar")?;
+        let mut second = [0u8; 16];
+        generator.fill(&mut second);
+        crate::selftest::ensure(first != second, "salida repetida")?;
+        crate::selftest::ensure_eq(generator.bytes_out, 32, "bytes entregados")?;
+        Ok(())
+    }
+
+    fn reseed_after_enough_bits() {
+        let mut generator = Generator::new();
+        generator.mix(b"test", b"a", RESEED_BITS - 1);
+        let mut out = [0u8; 8];
+        generator.fill(&mut out);
+        crate::selftest::ensure_eq(generator.reseeds, 0, "resembrado antes de tiempo")?;
+        generator.mix(b"test", b"b", 1);
+        generator.fill(&mut out);
+        crate::selftest::ensure_eq(generator.reseeds, 1, "resembrados")?;
+        crate::selftest::ensure_eq(generator.pending_bits, 0, "bits pendientes")?;
+        Ok(())
+    }
+
+    fn getrandom_uses_pool() {
+        let mut out = [0u8; 48];
+        getrandom::getrandom(&mut out).map_err(|_| String::from("getrandom"))?;
+        crate::selftest::ensure(out != [0u8; 48], "salida a cero")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,273 @@
+//! Recovery mode: a console with the repair tools and nothing else.
+//!
+//! Chosen in the boot menu, or with `recovery` in the load options. The boot
+//! runs as usual up to the drivers but skips the installer, the network and
+//! the desktop, and mounts no volume. Everything the shell needs is in the
+//! kernel image the firmware already copied to RAM, so the installed volume
+//! can be checked, repaired or uninstalled while it runs, without the USB
+//! installer.
+//!
+//! The shell only takes `COMMANDS`; `continuar` starts the desktop as a
+//! normal boot would.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use uefi::fs::FileSystem as UefiFileSystem;
+use uefi::proto::media::fs::SimpleFileSystem;
+use uefi::CString16;
+
+use crate::bootvar::{Plan, State};
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// First words the recovery shell runs.
+pub const COMMANDS: &[&str] = &[
+    "help",
+    "disks",
+    "vols",
+    "mount",
+    "parts",
+    "ls",
+    "cd",
+    "cat",
+    "chkdsk",
+    "bootvar",
+    "osprober",
+    "crypt",
+    "uninstall",
+    "dmesg",
+    "log",
+    "lspci",
+    "hwinfo",
+    "reboot",
+    "continuar",
+];
+
+/// Files that mark a volume as a Zenox install; see
+/// `crate::handle_has_installed_redux_marker`.
+const MARKERS: [&str; 3] = ["\\ZENOXOS.INI", "\\GOOS.INI", "\\REDUXOS.INI"];
+const README: &str = "\\README.TXT";
+
+pub fn requested(load_options: Option<&str>) -> bool {
+    load_options
+        .map(|opts| opts.split_whitespace().any(|w| w.eq_ignore_ascii_case("recovery")))
+        .unwrap_or(false)
+}
+
+pub fn enter() {
+    ACTIVE.store(true, Ordering::Relaxed);
+}
+
+pub fn leave() {
+    ACTIVE.store(false, Ordering::Relaxed);
+}
+
+pub fn active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+pub fn allowed(cmd: &str) -> bool {
+    let verb = cmd.split_whitespace().next().unwrap_or("");
+    COMMANDS.contains(&verb)
+}
+
+pub fn help_lines() -> Vec<String> {
+    [
+        "Zenox OS - modo de recuperacion (sin escritorio, ningun volumen montado)",
+        "  disks | vols              discos y volumenes UEFI",
+        "  mount <n>, ls, cd, cat    montar y mirar un volumen FAT32/exFAT",
+        "  parts                     tablas de particiones de los discos internos",
+        "  chkdsk [reparar]          comprobar y reparar el volumen montado",
+        "  bootvar ...               entradas de arranque UEFI (bootvar sin nada las lista)",
+        "  osprober                  otros sistemas en los discos",
+        "  crypt ...                 particion de datos cifrada",
+        "  uninstall [n [--yes]]     quitar una instalacion de Zenox OS",
+        "  dmesg, lspci, hwinfo      registro y hardware",
+        "  continuar                 seguir al escritorio",
+        "  reboot                    reiniciar",
+    ]
+    .iter()
+    .map(|line| String::from(*line))
+    .collect()
+}
+
+/// Enter recovery mode and run its shell.
+pub fn run() -> ! {
+    enter();
+    crate::klog::log(crate::klog::Level::Warning, "recovery: modo de recuperacion");
+    for line in help_lines().iter() {
+        crate::println(line.as_str());
+    }
+    crate::println("");
+    crate::shell_loop(0)
+}
+
+/// Boot variable changes that uninstall the entries `on_volume`. The entry
+/// this boot came from can't be deleted (`Plan::check`), so it is only
+/// disabled.
+pub fn uninstall_plan(state: &State, on_volume: &[u16]) -> Plan {
+    let mut plan = Plan::new(state);
+    for id in on_volume.iter().copied() {
+        if state.current == Some(id) {
+            plan.set_active(id, false);
+        } else {
+            plan.delete(id);
+        }
+    }
+    plan
+}
+
+/// Marker files present on `handle`.
+fn markers_on(handle: uefi::Handle) -> Vec<&'static str> {
+    let mut out = Vec::new();
+    for path in MARKERS.iter().copied().chain(core::iter::once(README)) {
+        let Ok(name) = CString16::try_from(path) else {
+            continue;
+        };
+        let Some(raw) = crate::read_file_from_fs_handle(handle, name.as_ref()) else {
+            continue;
+        };
+        let text = String::from_utf8_lossy(raw.as_slice());
+        if path != README || text.contains("installed on internal storage.") {
+            out.push(path);
+        }
+    }
+    out
+}
+
+fn remove_markers(handle: uefi::Handle, markers: &[&str]) -> Result<(), String> {
+    let proto = uefi::boot::open_protocol_exclusive::<SimpleFileSystem>(handle)
+        .map_err(|_| String::from("no se pudo abrir el volumen"))?;
+    let mut fs = UefiFileSystem::new(proto);
+    for path in markers.iter() {
+        let name = CString16::try_from(*path).map_err(|_| String::from("ruta invalida"))?;
+        fs.remove_file(name.as_ref())
+            .map_err(|_| alloc::format!("no se pudo borrar {}", path))?;
+    }
+    Ok(())
+}
+
+/// `uninstall [n [--yes]]`: list the installs, show what removing one does,
+/// or do it. The partitions and their data stay; the installer can reuse or
+/// delete them.
+pub fn uninstall_lines(args: &str) -> Vec<String> {
+    if !active() {
+        return alloc::vec![String::from(
+            "uninstall: solo en modo de recuperacion (elige 'Modo de recuperacion' al arrancar)"
+        )];
+    }
+    let mut words: Vec<&str> = args.split_whitespace().collect();
+    let before = words.len();
+    words.retain(|word| *word != "-y" && *word != "--yes");
+    let confirmed = words.len() != before;
+
+    let installed = crate::find_installed_redux_handles(None);
+    let Some(index) = words.first() else {
+        if installed.is_empty() {
+            return alloc::vec![String::from("uninstall: no hay instalaciones de Zenox OS")];
+        }
+        let mut lines = alloc::vec![String::from("Instalaciones de Zenox OS:")];
+        for (index, handle) in installed.iter().enumerate() {
+            lines.push(alloc::format!(
+                "  {}) {}",
+                index + 1,
+                crate::installed_redux_handle_description(*handle, index + 1)
+            ));
+        }
+        lines.push(String::from(
+            "'uninstall <n>' muestra lo que se haria; con --yes lo hace.",
+        ));
+        return lines;
+    };
+    let Some(handle) = index
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_sub(1))
+        .and_then(|n| installed.get(n).copied())
+    else {
+        return alloc::vec![alloc::format!("uninstall: no hay instalacion {}", index)];
+    };
+
+    let state = match crate::bootvar::read_state() {
+        Ok(state) => state,
+        Err(err) => return alloc::vec![alloc::format!("uninstall: {}", err)],
+    };
+    let on_volume = crate::bootvar::handle_partition(handle)
+        .map(|partition| crate::bootvar::entries_on(&state, partition))
+        .unwrap_or_default();
+    let plan = uninstall_plan(&state, on_volume.as_slice());
+    let markers = markers_on(handle);
+
+    let mut lines = alloc::vec![alloc::format!(
+        "Quitar {}:",
+        crate::installed_redux_handle_description(handle, index.parse().unwrap_or(1))
+    )];
+    lines.extend(
+        plan.changes(&state)
+            .into_iter()
+            .map(|line| alloc::format!("  {}", line)),
+    );
+    lines.extend(markers.iter().map(|path| alloc::format!("  Borrar {}", path)));
+    lines.push(String::from(
+        "  Las particiones y sus datos se quedan; el instalador puede reutilizarlas o borrarlas.",
+    ));
+    if !confirmed {
+        lines.push(alloc::format!("Repite con 'uninstall {} --yes' para hacerlo.", index));
+        return lines;
+    }
+
+    if let Err(err) = plan.check(&state) {
+        lines.push(alloc::format!("uninstall: {}", err));
+        return lines;
+    }
+    match crate::bootvar::apply(&state, &plan) {
+        Ok(done) => lines.extend(done.into_iter().filter(|line| line.starts_with("Copia"))),
+        Err(err) => {
+            lines.push(alloc::format!("uninstall: {}", err));
+            return lines;
+        }
+    }
+    match remove_markers(handle, markers.as_slice()) {
+        Ok(()) => lines.push(String::from(
+            "Hecho: esta instalacion ya no aparece en el menu de arranque.",
+        )),
+        Err(err) => lines.push(alloc::format!("uninstall: {}", err)),
+    }
+    lines
+}
+
+crate::selftest::kernel_tests! {
+    "recovery";
+
+    fn recognises_option_and_commands() {
+        crate::selftest::ensure(requested(Some("quiet RECOVERY")), "opcion")?;
+        crate::selftest::ensure(!requested(Some("recoveryx")), "palabra entera")?;
+        crate::selftest::ensure(!requested(None), "sin opciones")?;
+        crate::selftest::ensure(allowed("chkdsk reparar"), "chkdsk")?;
+        crate::selftest::ensure(allowed("bootvar"), "bootvar")?;
+        crate::selftest::ensure(!allowed("gui"), "sin escritorio")?;
+        crate::selftest::ensure(!allowed("chkdskx"), "verbo exacto")
+    }
+
+    fn uninstall_keeps_the_current_entry() {
+        let entry = |id: u16| crate::bootvar::Entry {
+            id,
+            raw: alloc::vec![1, 0, 0, 0],
+        };
+        let state = State {
+            order: alloc::vec![1, 2, 3],
+            entries: alloc::vec![entry(1), entry(2), entry(3)],
+            next: Some(2),
+            current: Some(1),
+        };
+        let plan = uninstall_plan(&state, &[1, 2]);
+        crate::selftest::ensure_eq(plan.deleted.clone(), alloc::vec![2], "borradas")?;
+        crate::selftest::ensure(!plan.is_active(1), "actual desactivada")?;
+        crate::selftest::ensure_eq(plan.next, None, "sin BootNext")?;
+        crate::selftest::ensure(plan.check(&state).is_ok(), "plan valido")?;
+        let all = uninstall_plan(&state, &[1, 2, 3]);
+        crate::selftest::ensure(all.check(&state).is_err(), "sin ninguna entrada activa")
+    }
+}
@@ -0,0 +1,775 @@
+//! Record and replay of a desktop session: input and network replies.
+//!
+//! `replay record` arms a recording for the next boot. From the moment the
+//! desktop starts, every key and pointer report `crate::input` hands out is
+//! stored with its time since desktop start, and so is every reply the HTTP
+//! clients (`HttpRequest::send`, `net::worker`) and the Gemini/Gopher client
+//! get back. The recording goes to `\REDUXOS\REPLAY.RRP` every few seconds,
+//! so a crash or a hang loses at most the last seconds of it.
+//!
+//! Copying that file to another machine (or a QEMU disk) and booting with
+//! `replay` in the load options, or after `replay play`, feeds the same
+//! input at the same times from desktop start and answers every request from
+//! the recording instead of the network. Live input is dropped until the
+//! recording runs out. Replays need a network device for the background
+//! client to run, but nothing goes out on it.
+//!
+//! Both modes last one boot. Recordings hold everything typed, passwords
+//! included, and the pages fetched.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use crate::config::ConfigValue;
+use crate::input::{RuntimeInput, RuntimeKey};
+use crate::spinlock::SpinLock;
+
+const MAGIC: &[u8; 4] = b"RRPL";
+const VERSION: u8 = 1;
+const DIR: &str = "REDUXOS";
+const FILE: &str = "REPLAY.RRP";
+/// `record` or `play`: the mode of the next desktop session.
+pub const MODE_KEY: &str = "replay.mode";
+/// Pending bytes go to disk this often.
+const FLUSH_MS: u64 = 5_000;
+/// The file is rewritten whole on each flush; recording stops past this.
+const MAX_BYTES: usize = 16 * 1024 * 1024;
+
+const TAG_KEY: u8 = 1;
+const TAG_POINTER: u8 = 2;
+const TAG_NET: u8 = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Off,
+    Record,
+    Play,
+}
+
+impl Mode {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Mode::Record,
+            2 => Mode::Play,
+            _ => Mode::Off,
+        }
+    }
+}
+
+/// One report from `crate::input::poll_mouse_uefi`, with the tablet
+/// position `take_absolute_pointer` returns after it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Pointer {
+    pub dx: i32,
+    pub dy: i32,
+    pub wheel: i32,
+    pub left: bool,
+    pub right: bool,
+    pub absolute: Option<(i32, i32)>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Event {
+    /// A key and `crate::input::last_key_repeat` for it.
+    Key {
+        input: RuntimeInput,
+        repeat: Option<bool>,
+    },
+    Pointer(Pointer),
+    /// Reply to the request `key` (see `HttpRequest::replay_key`), `None`
+    /// when it failed.
+    Net {
+        key: String,
+        reply: Option<Vec<u8>>,
+    },
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Record {
+    /// Milliseconds since desktop start.
+    pub ms: u64,
+    pub event: Event,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Recording {
+    /// Screen size while recording, to scale tablet positions.
+    pub screen: (u32, u32),
+    pub records: Vec<Record>,
+}
+
+fn key_code(input: &RuntimeInput) -> (u8, u32) {
+    match *input {
+        RuntimeInput::Char(ch) => (0, ch as u32),
+        RuntimeInput::Enter => (1, 0),
+        RuntimeInput::Backspace => (2, 0),
+        RuntimeInput::Key(key) => (
+            3,
+            match key {
+                RuntimeKey::Esc => 0,
+                RuntimeKey::F1 => 1,
+                RuntimeKey::F2 => 2,
+                RuntimeKey::F12 => 3,
+                RuntimeKey::Up => 4,
+                RuntimeKey::Down => 5,
+                RuntimeKey::Left => 6,
+                RuntimeKey::Right => 7,
+                RuntimeKey::PageUp => 8,
+                RuntimeKey::PageDown => 9,
+                RuntimeKey::SuperSpace => 10,
+                // 11..=19 Super+1..9, 20..=28 with Shift.
+                RuntimeKey::SuperDigit { digit, shift } => 10 + digit as u32 + if shift { 9 } else { 0 },
+            },
+        ),
+        // Not recorded, see `record_key`.
+        RuntimeInput::Mouse { .. } => (4, 0),
+    }
+}
+
+fn key_from_code(kind: u8, value: u32) -> Option<RuntimeInput> {
+    const KEYS: [RuntimeKey; 11] = [
+        RuntimeKey::Esc,
+        RuntimeKey::F1,
+        RuntimeKey::F2,
+        RuntimeKey::F12,
+        RuntimeKey::Up,
+        RuntimeKey::Down,
+        RuntimeKey::Left,
+        RuntimeKey::Right,
+        RuntimeKey::PageUp,
+        RuntimeKey::PageDown,
+        RuntimeKey::SuperSpace,
+    ];
+    match kind {
+        0 => char::from_u32(value).map(RuntimeInput::Char),
+        1 => Some(RuntimeInput::Enter),
+        2 => Some(RuntimeInput::Backspace),
+        3 if (11..=28).contains(&value) => Some(RuntimeInput::Key(RuntimeKey::SuperDigit {
+            digit: ((value - 11) % 9 + 1) as u8,
+            shift: value >= 20,
+        })),
+        3 => KEYS.get(value as usize).copied().map(RuntimeInput::Key),
+        _ => None,
+    }
+}
+
+pub fn encode_header(screen: (u32, u32)) -> Vec<u8> {
+    let mut out = Vec::with_capacity(13);
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    out.extend_from_slice(&screen.0.to_le_bytes());
+    out.extend_from_slice(&screen.1.to_le_bytes());
+    out
+}
+
+pub fn encode_record(out: &mut Vec<u8>, record: &Record) {
+    let tag = match record.event {
+        Event::Key { .. } => TAG_KEY,
+        Event::Pointer(_) => TAG_POINTER,
+        Event::Net { .. } => TAG_NET,
+    };
+    out.push(tag);
+    out.extend_from_slice(&record.ms.to_le_bytes());
+    match &record.event {
+        Event::Key { input, repeat } => {
+            let (kind, value) = key_code(input);
+            out.push(kind);
+            out.extend_from_slice(&value.to_le_bytes());
+            out.push(match repeat {
+                None => 0,
+                Some(false) => 1,
+                Some(true) => 2,
+            });
+        }
+        Event::Pointer(p) => {
+            for v in [p.dx, p.dy, p.wheel] {
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            out.push(p.left as u8 | (p.right as u8) << 1 | (p.absolute.is_some() as u8) << 2);
+            let (x, y) = p.absolute.unwrap_or((0, 0));
+            out.extend_from_slice(&x.to_le_bytes());
+            out.extend_from_slice(&y.to_le_bytes());
+        }
+        Event::Net { key, reply } => {
+            let key = &key.as_bytes()[..key.len().min(u16::MAX as usize)];
+            out.extend_from_slice(&(key.len() as u16).to_le_bytes());
+            out.extend_from_slice(key);
+            match reply {
+                Some(bytes) => {
+                    out.push(1);
+                    out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+                    out.extend_from_slice(bytes);
+                }
+                None => out.push(0),
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    raw: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.raw.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        self.u32().map(|v| v as i32)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let b = self.take(8)?;
+        let mut word = [0u8; 8];
+        word.copy_from_slice(b);
+        Some(u64::from_le_bytes(word))
+    }
+
+    fn record(&mut self) -> Option<Record> {
+        let tag = self.u8()?;
+        let ms = self.u64()?;
+        let event = match tag {
+            TAG_KEY => {
+                let kind = self.u8()?;
+                let value = self.u32()?;
+                let repeat = match self.u8()? {
+                    1 => Some(false),
+                    2 => Some(true),
+                    _ => None,
+                };
+                Event::Key {
+                    input: key_from_code(kind, value)?,
+                    repeat,
+                }
+            }
+            TAG_POINTER => {
+                let (dx, dy, wheel) = (self.i32()?, self.i32()?, self.i32()?);
+                let flags = self.u8()?;
+                let (x, y) = (self.i32()?, self.i32()?);
+                Event::Pointer(Pointer {
+                    dx,
+                    dy,
+                    wheel,
+                    left: flags & 1 != 0,
+                    right: flags & 2 != 0,
+                    absolute: (flags & 4 != 0).then_some((x, y)),
+                })
+            }
+            TAG_NET => {
+                let len = self.u16()? as usize;
+                let key = String::from_utf8_lossy(self.take(len)?).into_owned();
+                let reply = match self.u8()? {
+                    0 => None,
+                    _ => {
+                        let len = self.u32()? as usize;
+                        Some(self.take(len)?.to_vec())
+                    }
+                };
+                Event::Net { key, reply }
+            }
+            _ => return None,
+        };
+        Some(Record { ms, event })
+    }
+}
+
+impl Recording {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = encode_header(self.screen);
+        for record in self.records.iter() {
+            encode_record(&mut out, record);
+        }
+        out
+    }
+
+    /// Parse a recording. A cut-off last record (the machine died during a
+    /// flush) is dropped; everything before it is kept.
+    pub fn decode(raw: &[u8]) -> Result<Self, &'static str> {
+        let mut reader = Reader { raw, pos: 0 };
+        if reader.take(4) != Some(MAGIC.as_slice()) {
+            return Err("no es una grabacion");
+        }
+        if reader.u8() != Some(VERSION) {
+            return Err("version de grabacion no soportada");
+        }
+        let screen = (
+            reader.u32().ok_or("cabecera cortada")?,
+            reader.u32().ok_or("cabecera cortada")?,
+        );
+        let mut records = Vec::new();
+        while let Some(record) = reader.record() {
+            records.push(record);
+        }
+        Ok(Self { screen, records })
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        let (mut keys, mut pointer, mut net, mut net_bytes) = (0usize, 0usize, 0usize, 0usize);
+        for record in self.records.iter() {
+            match &record.event {
+                Event::Key { .. } => keys += 1,
+                Event::Pointer(_) => pointer += 1,
+                Event::Net { reply, .. } => {
+                    net += 1;
+                    net_bytes += reply.as_ref().map(|r| r.len()).unwrap_or(0);
+                }
+            }
+        }
+        let last = self.records.last().map(|r| r.ms).unwrap_or(0);
+        alloc::vec![
+            alloc::format!(
+                "Grabacion: {}.{:03} s, pantalla {}x{}",
+                last / 1000,
+                last % 1000,
+                self.screen.0,
+                self.screen.1
+            ),
+            alloc::format!(
+                "  {} teclas, {} eventos de puntero, {} respuestas de red ({} KiB)",
+                keys,
+                pointer,
+                net,
+                net_bytes / 1024
+            ),
+        ]
+    }
+}
+
+/// Playback cursor over a loaded recording.
+struct Player {
+    keys: VecDeque<Record>,
+    pointer: VecDeque<Record>,
+    net: Vec<(String, Option<Vec<u8>>)>,
+    /// (recorded, current) screen sizes.
+    screen: ((u32, u32), (u32, u32)),
+}
+
+impl Player {
+    fn new(recording: Recording, screen: (u32, u32)) -> Self {
+        let mut player = Self {
+            keys: VecDeque::new(),
+            pointer: VecDeque::new(),
+            net: Vec::new(),
+            screen: (recording.screen, screen),
+        };
+        for record in recording.records.into_iter() {
+            match record.event {
+                Event::Key { .. } => player.keys.push_back(record),
+                Event::Pointer(_) => player.pointer.push_back(record),
+                Event::Net { key, reply } => player.net.push((key, reply)),
+            }
+        }
+        player
+    }
+
+    fn input_done(&self) -> bool {
+        self.keys.is_empty() && self.pointer.is_empty()
+    }
+
+    fn next_key(&mut self, now_ms: u64) -> Option<(RuntimeInput, Option<bool>)> {
+        if self.keys.front()?.ms > now_ms {
+            return None;
+        }
+        match self.keys.pop_front()?.event {
+            Event::Key { input, repeat } => Some((input, repeat)),
+            _ => None,
+        }
+    }
+
+    fn next_pointer(&mut self, now_ms: u64) -> Option<Pointer> {
+        if self.pointer.front()?.ms > now_ms {
+            return None;
+        }
+        let Event::Pointer(mut p) = self.pointer.pop_front()?.event else {
+            return None;
+        };
+        let ((from_w, from_h), (to_w, to_h)) = self.screen;
+        if let Some((x, y)) = p.absolute {
+            p.absolute = Some((scale(x, from_w, to_w), scale(y, from_h, to_h)));
+        }
+        Some(p)
+    }
+
+    /// Replies are matched by key in recording order, so the n-th request
+    /// for a URL gets the n-th reply recorded for it.
+    fn reply(&mut self, key: &str) -> Option<Option<Vec<u8>>> {
+        let index = self.net.iter().position(|(k, _)| k == key)?;
+        Some(self.net.remove(index).1)
+    }
+}
+
+fn scale(v: i32, from: u32, to: u32) -> i32 {
+    if from == 0 || from == to {
+        return v;
+    }
+    (v as i64 * to as i64 / from as i64) as i32
+}
+
+struct Session {
+    base_ms: u64,
+    /// Recording: the whole file so far.
+    bytes: Vec<u8>,
+    /// `bytes.len()` at the last flush.
+    flushed: usize,
+    player: Option<Player>,
+}
+
+static MODE: AtomicU8 = AtomicU8::new(0);
+static PLAY_REQUESTED: AtomicBool = AtomicBool::new(false);
+static SESSION: SpinLock<Session> = SpinLock::new(Session {
+    base_ms: 0,
+    bytes: Vec::new(),
+    flushed: 0,
+    player: None,
+});
+
+pub fn mode() -> Mode {
+    Mode::from_u8(MODE.load(Ordering::Relaxed))
+}
+
+fn set_mode(mode: Mode) {
+    MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+pub fn playing() -> bool {
+    mode() == Mode::Play
+}
+
+/// `replay` in the load options plays `\REDUXOS\REPLAY.RRP` this boot.
+pub fn note_load_options(load_options: Option<&str>) {
+    let wanted = load_options
+        .map(|opts| opts.split_whitespace().any(|w| w.eq_ignore_ascii_case("replay")))
+        .unwrap_or(false);
+    PLAY_REQUESTED.store(wanted, Ordering::Relaxed);
+}
+
+fn screen() -> (u32, u32) {
+    let (w, h) = crate::framebuffer::dimensions();
+    (w as u32, h as u32)
+}
+
+fn fat() -> Result<&'static mut crate::fat32::Fat32, &'static str> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    if fat.init_status != crate::fat32::InitStatus::Success {
+        return Err("volumen de arranque no montado");
+    }
+    Ok(fat)
+}
+
+fn load() -> Result<Recording, &'static str> {
+    let fat = fat()?;
+    let dir = fat.ensure_subdirectory(fat.root_cluster, DIR)?;
+    let raw = fat.read_file_in_dir(dir, FILE).map_err(|_| "no hay grabacion")?;
+    Recording::decode(raw.as_slice())
+}
+
+fn write(bytes: &[u8]) -> Result<(), &'static str> {
+    let fat = fat()?;
+    let dir = fat.ensure_subdirectory(fat.root_cluster, DIR)?;
+    fat.write_text_file_in_dir(dir, FILE, bytes)
+}
+
+/// Called once when the desktop takes input: start the mode asked for by
+/// the load options or by `replay record|play` in the last session.
+pub fn start() {
+    let pending = crate::config::get_str(MODE_KEY, "");
+    if !pending.is_empty() {
+        let _ = crate::config::unset(MODE_KEY);
+    }
+    let wanted = if PLAY_REQUESTED.load(Ordering::Relaxed) || pending == "play" {
+        Mode::Play
+    } else if pending == "record" {
+        Mode::Record
+    } else {
+        return;
+    };
+
+    let mut session = SESSION.lock();
+    session.base_ms = crate::timer::now_ms();
+    match wanted {
+        Mode::Play => match load() {
+            Ok(recording) => {
+                if recording.screen != screen() {
+                    crate::klog::log(
+                        crate::klog::Level::Warning,
+                        "replay: la pantalla no tiene el tamano de la grabacion; el puntero se escala",
+                    );
+                }
+                session.player = Some(Player::new(recording, screen()));
+                set_mode(Mode::Play);
+                crate::klog::log(crate::klog::Level::Info, "replay: reproduciendo \\REDUXOS\\REPLAY.RRP");
+            }
+            Err(err) => crate::klog::log(crate::klog::Level::Error, alloc::format!("replay: {}", err).as_str()),
+        },
+        _ => {
+            session.bytes = encode_header(screen());
+            session.flushed = 0;
+            set_mode(Mode::Record);
+            crate::timer::every_ms(FLUSH_MS, on_flush_timer, 0);
+            crate::klog::log(crate::klog::Level::Info, "replay: grabando en \\REDUXOS\\REPLAY.RRP");
+        }
+    }
+}
+
+fn elapsed(session: &Session) -> u64 {
+    crate::timer::now_ms().saturating_sub(session.base_ms)
+}
+
+fn record(event: Event) {
+    let mut session = SESSION.lock();
+    let ms = elapsed(&session);
+    let mut bytes = core::mem::take(&mut session.bytes);
+    encode_record(&mut bytes, &Record { ms, event });
+    let full = bytes.len() > MAX_BYTES;
+    session.bytes = bytes;
+    if full {
+        set_mode(Mode::Off);
+        drop(session);
+        crate::klog::log(
+            crate::klog::Level::Warning,
+            "replay: grabacion llena, se guarda y se para",
+        );
+    }
+}
+
+pub fn record_key(input: RuntimeInput, repeat: Option<bool>) {
+    // Only PS/2 polling in the text runtime reports the pointer as a key.
+    if mode() == Mode::Record && !matches!(input, RuntimeInput::Mouse { .. }) {
+        record(Event::Key { input, repeat });
+    }
+}
+
+pub fn record_pointer(pointer: Pointer) {
+    if mode() == Mode::Record {
+        record(Event::Pointer(pointer));
+    }
+}
+
+pub fn record_reply(key: &str, reply: Option<&[u8]>) {
+    if mode() == Mode::Record {
+        record(Event::Net {
+            key: String::from(key),
+            reply: reply.map(|r| r.to_vec()),
+        });
+    }
+}
+
+/// Playing: the next recorded key that is due, with its repeat origin.
+pub fn next_key() -> Option<(RuntimeInput, Option<bool>)> {
+    let mut session = SESSION.lock();
+    let now = elapsed(&session);
+    let key = session.player.as_mut()?.next_key(now);
+    finish_if_done(&mut session);
+    key
+}
+
+/// Playing: the next recorded pointer report that is due.
+pub fn next_pointer() -> Option<Pointer> {
+    let mut session = SESSION.lock();
+    let now = elapsed(&session);
+    let pointer = session.player.as_mut()?.next_pointer(now);
+    finish_if_done(&mut session);
+    pointer
+}
+
+fn finish_if_done(session: &mut Session) {
+    if session.player.as_ref().is_some_and(|p| p.input_done()) {
+        session.player = None;
+        set_mode(Mode::Off);
+        crate::klog::log(
+            crate::klog::Level::Info,
+            "replay: grabacion terminada, vuelve la entrada real",
+        );
+    }
+}
+
+/// Playing: the recorded reply for request `key`, `Some(None)` when it
+/// failed or was never made while recording. `None` when not playing.
+pub fn network_reply(key: &str) -> Option<Option<Vec<u8>>> {
+    if !playing() {
+        return None;
+    }
+    let mut session = SESSION.lock();
+    let player = session.player.as_mut()?;
+    Some(player.reply(key).unwrap_or_else(|| {
+        crate::klog::log(
+            crate::klog::Level::Warning,
+            alloc::format!("replay: sin respuesta grabada para {}", key).as_str(),
+        );
+        None
+    }))
+}
+
+/// Write what was recorded since the last flush.
+pub fn flush() -> Result<(), &'static str> {
+    let bytes = {
+        let session = SESSION.lock();
+        if session.bytes.len() == session.flushed {
+            return Ok(());
+        }
+        session.bytes.clone()
+    };
+    write(bytes.as_slice())?;
+    SESSION.lock().flushed = bytes.len();
+    Ok(())
+}
+
+fn on_flush_timer(_arg: usize) {
+    if let Err(err) = flush() {
+        crate::klog::log(crate::klog::Level::Error, alloc::format!("replay: {}", err).as_str());
+    }
+}
+
+/// Stop recording or playing; a recording is written out first.
+pub fn stop() -> Result<(), &'static str> {
+    let was = mode();
+    set_mode(Mode::Off);
+    SESSION.lock().player = None;
+    if was == Mode::Record {
+        flush()?;
+    }
+    Ok(())
+}
+
+/// Shared implementation of `replay [info|record|play|stop]`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    match args.trim() {
+        "" | "info" => {
+            let session = SESSION.lock();
+            let status = match mode() {
+                Mode::Off => String::from("Replay: inactivo"),
+                Mode::Record => alloc::format!(
+                    "Replay: grabando desde hace {} s ({} KiB)",
+                    elapsed(&session) / 1000,
+                    session.bytes.len() / 1024
+                ),
+                Mode::Play => {
+                    let left = session
+                        .player
+                        .as_ref()
+                        .map(|p| p.keys.len() + p.pointer.len())
+                        .unwrap_or(0);
+                    alloc::format!(
+                        "Replay: reproduciendo desde hace {} s ({} eventos por llegar)",
+                        elapsed(&session) / 1000,
+                        left
+                    )
+                }
+            };
+            drop(session);
+            out.push(status);
+            let pending = crate::config::get_str(MODE_KEY, "");
+            if !pending.is_empty() {
+                out.push(alloc::format!("Proximo arranque: {}", pending));
+            }
+            if mode() != Mode::Record {
+                match load() {
+                    Ok(recording) => out.extend(recording.describe()),
+                    Err(err) => out.push(alloc::format!("replay: {}", err)),
+                }
+            }
+        }
+        "record" => match crate::config::set(MODE_KEY, ConfigValue::Str(String::from("record"))) {
+            Ok(()) => {
+                out.push(String::from(
+                    "replay: se grabara desde que arranque el escritorio la proxima vez, en \\REDUXOS\\REPLAY.RRP",
+                ));
+                out.push(String::from(
+                    "replay: la grabacion incluye todo lo tecleado (tambien contrasenas) y las paginas descargadas",
+                ));
+            }
+            Err(err) => out.push(alloc::format!("replay: {}", err)),
+        },
+        "play" => match load() {
+            Ok(_) => match crate::config::set(MODE_KEY, ConfigValue::Str(String::from("play"))) {
+                Ok(()) => out.push(String::from(
+                    "replay: la grabacion se reproducira en el proximo arranque (o arranca con 'replay' en las opciones)",
+                )),
+                Err(err) => out.push(alloc::format!("replay: {}", err)),
+            },
+            Err(err) => out.push(alloc::format!("replay: {}", err)),
+        },
+        "stop" => {
+            let _ = crate::config::unset(MODE_KEY);
+            let was = mode();
+            match stop() {
+                Ok(()) if was == Mode::Record => out.push(String::from("replay: grabacion guardada")),
+                Ok(()) => out.push(String::from("replay: parado")),
+                Err(err) => out.push(alloc::format!("replay: {}", err)),
+            }
+        }
+        _ => out.push(String::from("Uso: replay [info|record|play|stop]")),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "replay";
+
+    fn round_trips_and_tolerates_a_cut_tail() {
+        let recording = Recording {
+            screen: (1280, 800),
+            records: alloc::vec![
+                Record { ms: 5, event: Event::Key { input: RuntimeInput::Char('n'), repeat: None } },
+                Record { ms: 9, event: Event::Key { input: RuntimeInput::Key(RuntimeKey::SuperSpace), repeat: Some(true) } },
+                Record {
+                    ms: 10,
+                    event: Event::Key { input: RuntimeInput::Key(RuntimeKey::SuperDigit { digit: 3, shift: true }), repeat: None },
+                },
+                Record {
+                    ms: 12,
+                    event: Event::Pointer(Pointer { dx: -3, dy: 4, wheel: 1, left: true, right: false, absolute: Some((640, 400)) }),
+                },
+                Record { ms: 30, event: Event::Net { key: String::from("GET http://h/"), reply: Some(alloc::vec![1, 2, 3]) } },
+                Record { ms: 31, event: Event::Net { key: String::from("GET http://h/x"), reply: None } },
+            ],
+        };
+        let raw = recording.encode();
+        crate::selftest::ensure_eq(Recording::decode(raw.as_slice()), Ok(recording.clone()), "ida y vuelta")?;
+        let cut = Recording::decode(&raw[..raw.len() - 3]).map_err(|_| "cola cortada")?;
+        crate::selftest::ensure_eq(cut.records.len(), 5, "ultimo registro descartado")?;
+        crate::selftest::ensure(Recording::decode(b"RRPX").is_err(), "cabecera")
+    }
+
+    fn plays_input_on_time_and_replies_by_key() {
+        let pointer = Pointer { dx: 0, dy: 0, wheel: 0, left: false, right: false, absolute: Some((100, 50)) };
+        let recording = Recording {
+            screen: (200, 100),
+            records: alloc::vec![
+                Record { ms: 10, event: Event::Key { input: RuntimeInput::Enter, repeat: None } },
+                Record { ms: 20, event: Event::Pointer(pointer) },
+                Record { ms: 25, event: Event::Net { key: String::from("GET a"), reply: Some(alloc::vec![1]) } },
+                Record { ms: 26, event: Event::Net { key: String::from("GET a"), reply: Some(alloc::vec![2]) } },
+            ],
+        };
+        let mut player = Player::new(recording, (400, 200));
+        crate::selftest::ensure(player.next_key(9).is_none(), "aun no")?;
+        crate::selftest::ensure_eq(player.next_key(10), Some((RuntimeInput::Enter, None)), "tecla")?;
+        crate::selftest::ensure(player.next_pointer(19).is_none(), "puntero aun no")?;
+        let moved = player.next_pointer(40).ok_or("puntero")?;
+        crate::selftest::ensure_eq(moved.absolute, Some((200, 100)), "escalado")?;
+        crate::selftest::ensure(player.input_done(), "sin entrada")?;
+        crate::selftest::ensure_eq(player.reply("GET a"), Some(Some(alloc::vec![1])), "primera")?;
+        crate::selftest::ensure_eq(player.reply("GET a"), Some(Some(alloc::vec![2])), "segunda")?;
+        crate::selftest::ensure_eq(player.reply("GET a"), None, "sin mas")
+    }
+}
@@ -996,6 +996,38 @@ impl Parser {
                 let rendered = args[0].render();
                 Ok(Value::Int(rendered.chars().count() as i64))
             }
+            "http_get" => {
+                if args.len() != 1 {
+                    return Err(alloc::format!("line {}: http_get(url) expects one argument", self.current_line()));
+                }
+                let (_, body) = self.http_fetch(name, "GET", &args[0].render(), "", "")?;
+                Ok(Value::Str(body))
+            }
+            "http_status" => {
+                if args.len() != 1 {
+                    return Err(alloc::format!("line {}: http_status(url) expects one argument", self.current_line()));
+                }
+                let (status, _) = self.http_fetch(name, "HEAD", &args[0].render(), "", "")?;
+                Ok(Value::Int(status as i64))
+            }
+            "http_request" => {
+                if args.len() < 2 || args.len() > 4 {
+                    return Err(alloc::format!(
+                        "line {}: http_request(method, url[, body[, headers]]) expects 2 to 4 arguments",
+                        self.current_line()
+                    ));
+                }
+                let body = args.get(2).map(|v| v.render()).unwrap_or_default();
+                let headers = args.get(3).map(|v| v.render()).unwrap_or_default();
+                let (_, body) = self.http_fetch(
+                    name,
+                    &args[0].render(),
+                    &args[1].render(),
+                    headers.as_str(),
+                    body.as_str(),
+                )?;
+                Ok(Value::Str(body))
+            }
             _ => Err(alloc::format!(
                 "line {}: unsupported function '{}'",
                 self.current_line(),
@@ -1004,6 +1036,22 @@ impl Parser {
         }
     }
 
+    // Scripts go through the same userspace HTTP API as ring-3 programs, so
+    // the firewall and the network quota ("reduxlang") apply to them too.
+    fn http_fetch(
+        &self,
+        name: &str,
+        method: &str,
+        url: &str,
+        headers: &str,
+        body: &str,
+    ) -> Result<(u16, String), String> {
+        match crate::net::redux_http::fetch("reduxlang", method, url, headers, body.as_bytes()) {
+            Ok((status, bytes)) => Ok((status, String::from_utf8_lossy(bytes.as_slice()).into_owned())),
+            Err(e) => Err(alloc::format!("line {}: {}: {}", self.current_line(), name, e.as_str())),
+        }
+    }
+
     fn values_equal(&self, left: &Value, right: &Value) -> bool {
         match (left, right) {
             (Value::Nil, Value::Nil) => true,
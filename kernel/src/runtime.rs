@@ -1,4 +1,4 @@
-use crate::{framebuffer, input, interrupts, memory, privilege, process, scheduler, syscall, timer, ui};
+use crate::{fbcon, framebuffer, input, interrupts, klog, memory, privilege, process, scheduler, syscall, timer, ui};
 use crate::framebuffer::FramebufferInfo;
 use crate::hal::pause;
 use crate::input::{RuntimeInput, RuntimeKey};
@@ -26,6 +26,7 @@ const ENABLE_EXPERIMENTAL_IRQ_PATH: bool = true;
 // Keep enabled so systems where PIC/PIT only emits one startup IRQ can still run in IRQ mode.
 const ENABLE_APIC_TIMER_FALLBACK: bool = true;
 const ENABLE_EXPERIMENTAL_PRIVILEGE_LAYERS: bool = true;
+const DMESG_DEFAULT_LINES: usize = 20;
 const RUNTIME_MODE_SWITCH_NONE: u8 = 0;
 const RUNTIME_MODE_SWITCH_POLLING: u8 = 1;
 const RUNTIME_MODE_SWITCH_IRQ: u8 = 2;
@@ -139,6 +140,39 @@ fn is_boot_command(cmd: &[u8]) -> bool {
     cmd.len() == 4 || cmd[4] == b' '
 }
 
+/// `dmesg [n]`: the ring-3 shell cannot reach the kernel log, so the
+/// runtime loop answers it itself. Returns the number of records to show.
+fn dmesg_command(cmd: &[u8]) -> Option<usize> {
+    let name = b"dmesg";
+    if cmd.len() < name.len() || !cmd.iter().zip(name.iter()).all(|(a, b)| ascii_lower(*a) == *b) {
+        return None;
+    }
+    let rest = &cmd[name.len()..];
+    if rest.is_empty() {
+        return Some(DMESG_DEFAULT_LINES);
+    }
+    if rest[0] != b' ' {
+        return None;
+    }
+    let arg = core::str::from_utf8(rest).ok()?.trim();
+    if arg.is_empty() {
+        return Some(DMESG_DEFAULT_LINES);
+    }
+    arg.parse::<usize>().ok().filter(|n| *n > 0)
+}
+
+fn print_dmesg(count: usize) {
+    for record in klog::tail(count).iter() {
+        let color = match record.level {
+            level if level <= klog::Level::Error => "\x1b[31m",
+            klog::Level::Warning => "\x1b[33m",
+            klog::Level::Debug => "\x1b[90m",
+            _ => "",
+        };
+        fbcon::write_line(alloc::format!("{}{}\x1b[0m", color, klog::format_record(record)).as_str());
+    }
+}
+
 #[inline]
 fn irq_trace(enabled: bool, msg: &'static str) {
     if enabled {
@@ -266,6 +300,7 @@ pub fn enter_runtime(
     syscall::init();
     process::init_user_space();
     process::reset_irq_preempt_hints();
+    fbcon::set_active(true);
     ui::terminal_reset(mode == RuntimeMode::IrqSafe);
     if let Some(note) = irq_fallback_note {
         ui::terminal_system_message(note);
@@ -315,6 +350,14 @@ pub fn enter_runtime(
                         alt_theme = !alt_theme;
                         force_render = true;
                     }
+                    RuntimeKey::PageUp => {
+                        ui::terminal_page_up();
+                        force_render = true;
+                    }
+                    RuntimeKey::PageDown => {
+                        ui::terminal_page_down();
+                        force_render = true;
+                    }
                     _ => {}
                 },
                 RuntimeInput::Char(ch) => {
@@ -334,7 +377,9 @@ pub fn enter_runtime(
                     let mut cmd = [0u8; ui::TERM_MAX_INPUT];
                     let n = ui::terminal_copy_input_trim(&mut cmd);
                     ui::terminal_commit_input_line();
-                    if n > 0 {
+                    if let Some(count) = dmesg_command(&cmd[..n]) {
+                        print_dmesg(count);
+                    } else if n > 0 {
                         syscall::enqueue_command(&cmd[..n]);
                         // Keep boot mode switches out of this inline path so the UI can
                         // flush diagnostics before entering IRQ transition code.
@@ -395,6 +440,8 @@ pub fn enter_runtime(
             RuntimeMode::Polling => timer::on_tick(),
             RuntimeMode::IrqSafe => timer::ticks(),
         };
+        timer::run_due();
+        crate::executor::run_ready();
 
         if mode == RuntimeMode::IrqSafe {
             if tick == last_source_tick {
@@ -525,6 +572,7 @@ pub fn enter_runtime_uefi(framebuffer_info: FramebufferInfo, mem_stats: memory::
     syscall::init();
     process::init_user_space();
     process::reset_irq_preempt_hints();
+    fbcon::set_active(true);
     ui::terminal_reset(false);
     ui::terminal_system_message("BOOT: UEFI MODE (BootServices alive)");
     ui::terminal_system_message("INPUT: UEFI keyboard (USB OK)");
@@ -578,6 +626,14 @@ pub fn enter_runtime_uefi(framebuffer_info: FramebufferInfo, mem_stats: memory::
                         alt_theme = !alt_theme;
                         force_render = true;
                     }
+                    RuntimeKey::PageUp => {
+                        ui::terminal_page_up();
+                        force_render = true;
+                    }
+                    RuntimeKey::PageDown => {
+                        ui::terminal_page_down();
+                        force_render = true;
+                    }
                     _ => {}
                 },
                 RuntimeInput::Char(ch) => {
@@ -597,7 +653,9 @@ pub fn enter_runtime_uefi(framebuffer_info: FramebufferInfo, mem_stats: memory::
                     let mut cmd = [0u8; ui::TERM_MAX_INPUT];
                     let n = ui::terminal_copy_input_trim(&mut cmd);
                     ui::terminal_commit_input_line();
-                    if n > 0 {
+                    if let Some(count) = dmesg_command(&cmd[..n]) {
+                        print_dmesg(count);
+                    } else if n > 0 {
                         syscall::enqueue_command(&cmd[..n]);
                         process_tick = process_tick.saturating_add(1);
                         process::on_tick_core(0, process_tick);
@@ -644,6 +702,8 @@ pub fn enter_runtime_uefi(framebuffer_info: FramebufferInfo, mem_stats: memory::
             RuntimeMode::Polling => timer::on_tick(),
             RuntimeMode::IrqSafe => timer::ticks(),
         };
+        timer::run_due();
+        crate::executor::run_ready();
 
         if mode == RuntimeMode::IrqSafe {
             if tick == last_source_tick {
@@ -0,0 +1,56 @@
+//! Safe mode: boot with the minimal driver set when a driver hangs the
+//! normal boot.
+//!
+//! Chosen in the boot menu, or with `safe-mode` in `CMDLINE.TXT` or the load
+//! options. The PCI drivers in `SKIPPED_DRIVERS` are never registered and
+//! virtio-blk is not bound, so the GPU, Wi-Fi and audio stay untouched and
+//! storage goes through the firmware's BlockIO only. The installer and the
+//! desktop do not start on their own: the boot ends in the console shell,
+//! whose prompt says it is in safe mode. `gui` still opens the desktop,
+//! drawn in software.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// PCI drivers safe mode leaves out, by `PciDriver::name`.
+pub const SKIPPED_DRIVERS: [&str; 4] = ["nvme", "intel_xe", "intel_wifi", "hda"];
+
+pub fn enter() {
+    ACTIVE.store(true, Ordering::Relaxed);
+}
+
+pub fn active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Whether `pci` should register the driver called `name` on this boot.
+pub fn allows_driver(name: &str) -> bool {
+    !active() || !SKIPPED_DRIVERS.contains(&name)
+}
+
+pub fn banner_lines() -> Vec<String> {
+    alloc::vec![
+        String::from("*** MODO SEGURO ***"),
+        alloc::format!("Drivers omitidos: {}", SKIPPED_DRIVERS.join(", ")),
+        String::from("Discos por UEFI BlockIO; sin escritorio automatico ('gui' lo abre sin aceleracion)."),
+        String::from("Reinicia sin 'safe-mode' para volver al arranque normal."),
+    ]
+}
+
+crate::selftest::kernel_tests! {
+    "safemode";
+
+    fn skips_only_listed_drivers() {
+        let was = active();
+        enter();
+        let skipped = !allows_driver("intel_xe") && !allows_driver("nvme");
+        let kept = allows_driver("virtio") && allows_driver("xhci");
+        ACTIVE.store(was, Ordering::Relaxed);
+        crate::selftest::ensure(skipped, "xe y nvme omitidos")?;
+        crate::selftest::ensure(kept, "virtio y xhci siguen")?;
+        crate::selftest::ensure(was || allows_driver("intel_xe"), "arranque normal sin omitir")
+    }
+}
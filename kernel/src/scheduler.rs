@@ -185,3 +185,36 @@ pub fn on_tick(current_tick: u64) {
 pub fn snapshot() -> SchedulerSnapshot {
     unsafe { SCHEDULER.snapshot() }
 }
+
+crate::selftest::kernel_tests! {
+    "scheduler";
+
+    fn demo_run_invariants() {
+        let mut sched = Scheduler::new();
+        sched.reset_demo();
+        let ticks = 2_000u64;
+        for tick in 1..=ticks {
+            sched.on_tick(tick);
+            crate::selftest::ensure(sched.cursor < sched.task_count, "cursor fuera de rango")?;
+        }
+        let snap = sched.snapshot();
+        crate::selftest::ensure(snap.dispatches <= ticks, "mas de un despacho por tick")?;
+        let mut total_runs = 0u64;
+        for task in snap.tasks[..snap.task_count].iter() {
+            total_runs += task.runs;
+            if task.max_runs != 0 {
+                crate::selftest::ensure(task.runs <= task.max_runs, task.name)?;
+                crate::selftest::ensure(!task.active || task.runs < task.max_runs, "tarea agotada sigue activa")?;
+            }
+        }
+        crate::selftest::ensure_eq(total_runs, snap.dispatches, "runs vs dispatches")
+    }
+
+    fn capacity_is_bounded() {
+        let mut sched = Scheduler::new();
+        for _ in 0..MAX_TASKS + 4 {
+            sched.add(Task::demo("t", 1, 0));
+        }
+        crate::selftest::ensure_eq(sched.task_count, MAX_TASKS, "task_count")
+    }
+}
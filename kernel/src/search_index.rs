@@ -0,0 +1,893 @@
+//! Background file index behind the quick search overlay (Super+Space) and
+//! the `index` command.
+//!
+//! `service` walks the mounted volume a few directories per call and keeps
+//! one record per file and folder: its name, where it lives and, for text
+//! files, the distinct words of the first `TEXT_SCAN_BYTES`. The index is
+//! saved to `\REDUXOS\INDEX.DAT` on that volume, so after a reboot searches
+//! answer at once while a verification pass lists the folders again
+//! (contents are only read again for files whose cluster or size changed).
+//! Changes come from `crate::fswatch`: only the folders its events name
+//! are rescanned.
+//!
+//! Settings (the Configuracion pages and the config registry keys) are
+//! matched from a fixed table, without indexing.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::spinlock::SpinLock;
+
+const DIR: &str = "REDUXOS";
+const INDEX_FILE: &str = "INDEX.DAT";
+const MAGIC: &[u8; 4] = b"RXIX";
+const VERSION: u8 = 1;
+/// The walk stops adding records past this.
+const MAX_RECORDS: usize = 20_000;
+const MAX_DEPTH: usize = 16;
+/// Bytes read from the start of each text file.
+const TEXT_SCAN_BYTES: usize = 16 * 1024;
+const WORDS_PER_FILE: usize = 200;
+const WORD_MIN_BYTES: usize = 3;
+const WORD_MAX_BYTES: usize = 24;
+/// Folder listings or text files handled per `service` call.
+const WORK_PER_TICK: usize = 4;
+const TICK_MS: u64 = 15;
+/// Quiet time after the last change before the index is written.
+const SAVE_DELAY_MS: u64 = 5_000;
+/// Folders that hold system data rather than user files.
+const SKIPPED_DIRS: [&str; 4] = ["REDUXOS", "TRASH", "$RECYCLE.BIN", "SYSTEM VOLUME INFORMATION"];
+const TEXT_EXTENSIONS: [&str; 24] = [
+    "txt", "md", "log", "csv", "ini", "cfg", "conf", "json", "toml", "xml", "html", "htm", "css", "js", "rs", "c", "h",
+    "cpp", "hpp", "py", "rb", "sh", "rml", "bat",
+];
+
+/// Settings pages: label, extra words that find it, and the app that shows it.
+const SETTINGS: [(&str, &str, &str); 10] = [
+    ("Configuracion", "ajustes settings sistema preferencias", "settings"),
+    (
+        "Fondo y tema del escritorio",
+        "wallpaper fondo tema colores desktop apariencia",
+        "settings",
+    ),
+    (
+        "Color de pantalla y luz nocturna",
+        "gamma brillo temperatura night light display pantalla",
+        "settings",
+    ),
+    (
+        "Idioma y teclado",
+        "idioma language teclado keyboard repeticion distribucion",
+        "settings",
+    ),
+    ("Redes WiFi", "wifi wireless red internet conexion", "wifi"),
+    (
+        "Entradas de arranque UEFI",
+        "boot arranque bootorder uefi bootnext",
+        "bootvar",
+    ),
+    (
+        "Acerca de este PC",
+        "hardware sistema cpu memoria informacion equipo",
+        "aboutpc",
+    ),
+    (
+        "Administrador de tareas",
+        "procesos cpu memoria rendimiento taskmgr",
+        "taskmgr",
+    ),
+    ("Alarmas y temporizadores", "reloj alarma timer despertador", "clock"),
+    ("Cuenta de correo", "mail correo imap smtp cuenta", "mail"),
+];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Record {
+    /// Cluster of the folder holding it.
+    dir: u32,
+    cluster: u32,
+    size: u32,
+    is_dir: bool,
+    name: String,
+    /// Space-separated lowercase words of a text file.
+    terms: String,
+}
+
+/// One entry of a folder listing.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Listed {
+    name: String,
+    cluster: u32,
+    size: u32,
+    is_dir: bool,
+}
+
+struct Index {
+    /// Identity of the volume the records belong to; 0 before one is seen.
+    volume: u64,
+    root: u32,
+    /// Indexed folders: cluster -> path from the root ("/", "/DOCS/").
+    dirs: BTreeMap<u32, String>,
+    records: Vec<Record>,
+    pending_dirs: VecDeque<u32>,
+    /// Clusters of text files whose words are still to be read.
+    pending_texts: VecDeque<u32>,
+    /// Folders named by filesystem events since the last tick.
+    changed: Vec<u32>,
+    /// A first full walk is running; results are incomplete until it ends.
+    building: bool,
+    /// The record limit cut the walk short.
+    truncated: bool,
+    generation: u64,
+    saved_generation: u64,
+    last_change_ms: u64,
+    next_tick_ms: u64,
+}
+
+impl Index {
+    const fn new() -> Self {
+        Self {
+            volume: 0,
+            root: 0,
+            dirs: BTreeMap::new(),
+            records: Vec::new(),
+            pending_dirs: VecDeque::new(),
+            pending_texts: VecDeque::new(),
+            changed: Vec::new(),
+            building: false,
+            truncated: false,
+            generation: 0,
+            saved_generation: 0,
+            last_change_ms: 0,
+            next_tick_ms: 0,
+        }
+    }
+
+    /// Forget everything and queue a full walk from `root`.
+    fn restart(&mut self, volume: u64, root: u32) {
+        let (generation, next_tick_ms) = (self.generation, self.next_tick_ms);
+        *self = Self::new();
+        self.volume = volume;
+        self.root = root;
+        self.generation = generation + 1;
+        self.saved_generation = generation;
+        self.next_tick_ms = next_tick_ms;
+        self.dirs.insert(root, String::from("/"));
+        self.pending_dirs.push_back(root);
+        self.building = true;
+    }
+
+    fn touch(&mut self) {
+        self.generation += 1;
+        self.last_change_ms = crate::timer::now_ms();
+    }
+
+    /// Drop the folder at `path` (ending in '/') and everything below it.
+    fn remove_subtree(&mut self, path: &str) {
+        let gone: Vec<u32> = self
+            .dirs
+            .iter()
+            .filter(|(_, p)| p.starts_with(path))
+            .map(|(cluster, _)| *cluster)
+            .collect();
+        if gone.is_empty() {
+            return;
+        }
+        for cluster in gone.iter() {
+            self.dirs.remove(cluster);
+        }
+        self.records.retain(|r| !gone.contains(&r.dir));
+        self.pending_dirs.retain(|c| !gone.contains(c));
+        self.touch();
+    }
+
+    /// Replace the records of folder `dir` with a fresh listing. Unchanged
+    /// files keep their words; new text files and folders are queued.
+    fn apply_listing(&mut self, dir: u32, path: &str, listed: Vec<Listed>) {
+        let mut old = Vec::new();
+        let mut i = 0;
+        while i < self.records.len() {
+            if self.records[i].dir == dir {
+                old.push(self.records.swap_remove(i));
+            } else {
+                i += 1;
+            }
+        }
+        let mut changed = false;
+        for gone in old.iter().filter(|r| r.is_dir) {
+            if !listed
+                .iter()
+                .any(|l| l.is_dir && l.cluster == gone.cluster && l.name == gone.name)
+            {
+                self.remove_subtree(child_path(path, gone.name.as_str()).as_str());
+                changed = true;
+            }
+        }
+        let depth = path.matches('/').count();
+        let mut kept = 0usize;
+        for item in listed {
+            if item.is_dir && SKIPPED_DIRS.iter().any(|s| item.name.eq_ignore_ascii_case(s)) {
+                continue;
+            }
+            if self.records.len() >= MAX_RECORDS {
+                self.truncated = true;
+                break;
+            }
+            let previous = old.iter().find(|r| {
+                r.name == item.name && r.cluster == item.cluster && r.size == item.size && r.is_dir == item.is_dir
+            });
+            let terms = match previous {
+                Some(r) => {
+                    kept += 1;
+                    r.terms.clone()
+                }
+                None => {
+                    changed = true;
+                    if !item.is_dir && item.cluster >= 2 && item.size > 0 && is_text_name(item.name.as_str()) {
+                        self.pending_texts.push_back(item.cluster);
+                    }
+                    String::new()
+                }
+            };
+            if item.is_dir && item.cluster >= 2 && depth < MAX_DEPTH && !self.dirs.contains_key(&item.cluster) {
+                self.dirs.insert(item.cluster, child_path(path, item.name.as_str()));
+                self.pending_dirs.push_back(item.cluster);
+            }
+            self.records.push(Record {
+                dir,
+                cluster: item.cluster,
+                size: item.size,
+                is_dir: item.is_dir,
+                name: item.name,
+                terms,
+            });
+        }
+        if changed || kept != old.len() {
+            self.touch();
+        }
+    }
+
+    fn search(&self, query: &str, limit: usize) -> Vec<Hit> {
+        let query = ascii_lower(query.trim());
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let words: Vec<&str> = query.split_whitespace().collect();
+        let mut hits: Vec<Hit> = Vec::new();
+        for record in self.records.iter() {
+            let (score, in_contents) = match score_name(record.name.as_str(), query.as_str()) {
+                Some(score) => (score, false),
+                None if terms_match(record.terms.as_str(), &words) => (3_000 - record.name.len() as i32, true),
+                None => continue,
+            };
+            hits.push(Hit {
+                name: record.name.clone(),
+                dir_path: self.dirs.get(&record.dir).cloned().unwrap_or_else(|| String::from("/")),
+                dir_cluster: record.dir,
+                cluster: record.cluster,
+                size: record.size,
+                is_dir: record.is_dir,
+                in_contents,
+                score,
+            });
+        }
+        hits.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.name.cmp(&b.name)));
+        hits.truncate(limit);
+        hits
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.extend_from_slice(&self.volume.to_le_bytes());
+        out.extend_from_slice(&self.root.to_le_bytes());
+        out.extend_from_slice(&(self.dirs.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(self.records.len() as u32).to_le_bytes());
+        for (cluster, path) in self.dirs.iter() {
+            out.extend_from_slice(&cluster.to_le_bytes());
+            put_str(&mut out, path);
+        }
+        for r in self.records.iter() {
+            out.extend_from_slice(&r.dir.to_le_bytes());
+            out.extend_from_slice(&r.cluster.to_le_bytes());
+            out.extend_from_slice(&r.size.to_le_bytes());
+            out.push(r.is_dir as u8);
+            put_str(&mut out, r.name.as_str());
+            put_str(&mut out, r.terms.as_str());
+        }
+        let crc = crate::compress::crc32(&out);
+        out.extend_from_slice(&crc.to_le_bytes());
+        out
+    }
+
+    /// Index saved by `encode`; the walk queues are left empty.
+    fn decode(raw: &[u8]) -> Result<Self, &'static str> {
+        if raw.len() < 29 || &raw[..4] != MAGIC || raw[4] != VERSION {
+            return Err("formato de indice desconocido");
+        }
+        let (body, tail) = raw.split_at(raw.len() - 4);
+        if crate::compress::crc32(body) != u32::from_le_bytes([tail[0], tail[1], tail[2], tail[3]]) {
+            return Err("indice danado (CRC)");
+        }
+        let mut r = Reader { data: body, pos: 5 };
+        let mut index = Self::new();
+        index.volume = r.u64()?;
+        index.root = r.u32()?;
+        let dirs = r.u32()? as usize;
+        let records = r.u32()? as usize;
+        if records > MAX_RECORDS {
+            return Err("indice demasiado grande");
+        }
+        for _ in 0..dirs {
+            let cluster = r.u32()?;
+            let path = r.str()?;
+            index.dirs.insert(cluster, path);
+        }
+        for _ in 0..records {
+            index.records.push(Record {
+                dir: r.u32()?,
+                cluster: r.u32()?,
+                size: r.u32()?,
+                is_dir: r.u8()? != 0,
+                name: r.str()?,
+                terms: r.str()?,
+            });
+        }
+        Ok(index)
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], &'static str> {
+        let bytes = self.data.get(self.pos..self.pos + n).ok_or("indice truncado")?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, &'static str> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, &'static str> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, &'static str> {
+        Ok(self.u32()? as u64 | (self.u32()? as u64) << 32)
+    }
+
+    fn str(&mut self) -> Result<String, &'static str> {
+        let b = self.take(2)?;
+        let len = u16::from_le_bytes([b[0], b[1]]) as usize;
+        let bytes = self.take(len)?;
+        core::str::from_utf8(bytes)
+            .map(String::from)
+            .map_err(|_| "texto no valido en el indice")
+    }
+}
+
+fn put_str(out: &mut Vec<u8>, text: &str) {
+    let bytes = &text.as_bytes()[..text.len().min(u16::MAX as usize)];
+    out.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+static INDEX: SpinLock<Index> = SpinLock::new(Index::new());
+
+/// A file or folder found by `search`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Hit {
+    pub name: String,
+    /// Path of the folder holding it, ending in '/'.
+    pub dir_path: String,
+    pub dir_cluster: u32,
+    pub cluster: u32,
+    pub size: u32,
+    pub is_dir: bool,
+    /// Matched by the words inside the file rather than by its name.
+    pub in_contents: bool,
+    pub score: i32,
+}
+
+/// A settings page or config key found by `search_settings`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SettingHit {
+    pub label: String,
+    /// Current value of a config key; empty for pages.
+    pub detail: String,
+    /// App key of the window that shows it ("settings", "wifi"...).
+    pub app: &'static str,
+    pub score: i32,
+}
+
+pub struct Status {
+    pub records: usize,
+    pub folders: usize,
+    /// Text files whose words are indexed.
+    pub text_files: usize,
+    pub pending: usize,
+    pub building: bool,
+    pub truncated: bool,
+    pub unsaved: bool,
+}
+
+fn ascii_lower(text: &str) -> String {
+    let mut out = String::from(text);
+    out.make_ascii_lowercase();
+    out
+}
+
+fn child_path(parent: &str, name: &str) -> String {
+    alloc::format!("{}{}/", parent, name)
+}
+
+fn is_text_name(name: &str) -> bool {
+    name.rsplit_once('.')
+        .is_some_and(|(_, ext)| TEXT_EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+/// Name ranking shared with the Search window: exact, prefix, substring,
+/// then the query letters in order. `query` is already lowercase.
+fn score_name(name: &str, query: &str) -> Option<i32> {
+    let name = ascii_lower(name.trim());
+    if name.is_empty() || query.is_empty() {
+        return None;
+    }
+    let len = name.len() as i32;
+    if name == query {
+        return Some(10_000);
+    }
+    if name.starts_with(query) {
+        return Some(9_000 - len);
+    }
+    if let Some(pos) = name.find(query) {
+        return Some(8_000 - pos as i32 * 4 - len);
+    }
+    let mut wanted = query.chars().filter(|c| *c != ' ').peekable();
+    let (mut first, mut last) = (None, 0usize);
+    for (idx, ch) in name.chars().enumerate() {
+        if wanted.peek() == Some(&ch) {
+            wanted.next();
+            first.get_or_insert(idx);
+            last = idx;
+        }
+    }
+    if wanted.peek().is_some() {
+        return None;
+    }
+    let span = last + 1 - first.unwrap_or(0);
+    Some(6_000 - span as i32 * 3 - len)
+}
+
+/// Every query word of three or more bytes starts one of `terms`.
+fn terms_match(terms: &str, words: &[&str]) -> bool {
+    let mut any = false;
+    for word in words.iter().filter(|w| w.len() >= WORD_MIN_BYTES) {
+        if !terms.split(' ').any(|t| t.starts_with(word)) {
+            return false;
+        }
+        any = true;
+    }
+    any
+}
+
+/// Distinct lowercase words of a text, in order of first appearance.
+fn extract_terms(data: &[u8]) -> String {
+    let mut words: Vec<String> = Vec::new();
+    for chunk in data.split(|b| !(b.is_ascii_alphanumeric() || *b >= 0x80)) {
+        if chunk.len() < WORD_MIN_BYTES || chunk.len() > WORD_MAX_BYTES {
+            continue;
+        }
+        let Ok(word) = core::str::from_utf8(chunk) else {
+            continue;
+        };
+        let word = ascii_lower(word);
+        if !words.contains(&word) {
+            words.push(word);
+            if words.len() >= WORDS_PER_FILE {
+                break;
+            }
+        }
+    }
+    words.join(" ")
+}
+
+fn fs_ready() -> bool {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get() };
+    fat.init_status == crate::fat32::InitStatus::Success && fat.bytes_per_sector != 0
+}
+
+/// Identity of the mounted volume: label, partition offset and root.
+/// Follow the mounted volume: load its saved index (and verify it in the
+/// background) or start a walk. Returns false without a volume.
+fn ensure_volume() -> bool {
+    if !fs_ready() {
+        return false;
+    }
+    let (volume, root) = {
+        let fat = unsafe { crate::fat32::GLOBAL_FAT.get() };
+        (fat.volume_id(), fat.root_cluster)
+    };
+    if INDEX.lock().volume == volume {
+        return true;
+    }
+    let saved = {
+        let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+        fat.ensure_subdirectory(root, DIR)
+            .and_then(|dir| fat.read_file_in_dir(dir, INDEX_FILE))
+            .and_then(|raw| Index::decode(raw.as_slice()))
+    };
+    let mut ix = INDEX.lock();
+    match saved {
+        Ok(saved) if saved.volume == volume && saved.root == root => {
+            let next_tick_ms = ix.next_tick_ms;
+            *ix = saved;
+            ix.next_tick_ms = next_tick_ms;
+            let folders: Vec<u32> = ix.dirs.keys().copied().collect();
+            ix.pending_dirs.extend(folders);
+        }
+        _ => ix.restart(volume, root),
+    }
+    true
+}
+
+enum Job {
+    Dir(u32, String),
+    Text(u32, u32),
+}
+
+fn next_job() -> Option<Job> {
+    let mut guard = INDEX.lock();
+    let ix = &mut *guard;
+    for cluster in core::mem::take(&mut ix.changed) {
+        if !ix.pending_dirs.contains(&cluster) {
+            ix.pending_dirs.push_front(cluster);
+        }
+    }
+    while let Some(cluster) = ix.pending_dirs.pop_front() {
+        if let Some(path) = ix.dirs.get(&cluster) {
+            return Some(Job::Dir(cluster, path.clone()));
+        }
+    }
+    while let Some(cluster) = ix.pending_texts.pop_front() {
+        if let Some(record) = ix.records.iter().find(|r| r.cluster == cluster && !r.is_dir) {
+            return Some(Job::Text(cluster, record.size));
+        }
+    }
+    ix.building = false;
+    None
+}
+
+fn scan_dir(cluster: u32, path: &str) {
+    let listing = {
+        let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+        fat.read_dir_entries(cluster)
+    };
+    let mut ix = INDEX.lock();
+    match listing {
+        Ok(entries) => {
+            let listed = entries
+                .iter()
+                .filter(|e| e.valid)
+                .map(|e| Listed {
+                    name: e.full_name(),
+                    cluster: e.cluster,
+                    size: e.size,
+                    is_dir: e.file_type == crate::fs::FileType::Directory,
+                })
+                .filter(|l| l.name != "." && l.name != "..")
+                .collect();
+            ix.apply_listing(cluster, path, listed);
+        }
+        // The folder is gone or unreadable: forget it and what was below.
+        Err(_) if cluster != ix.root => ix.remove_subtree(path),
+        Err(_) => {}
+    }
+}
+
+fn read_terms(cluster: u32, size: u32) {
+    let mut buf = alloc::vec![0u8; (size as usize).min(TEXT_SCAN_BYTES)];
+    let read = {
+        let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+        fat.read_file_range(cluster, size as usize, 0, &mut buf)
+    };
+    let Ok(len) = read else {
+        return;
+    };
+    let terms = extract_terms(&buf[..len.min(buf.len())]);
+    let mut guard = INDEX.lock();
+    let ix = &mut *guard;
+    if let Some(record) = ix.records.iter_mut().find(|r| r.cluster == cluster && !r.is_dir) {
+        if record.terms != terms {
+            record.terms = terms;
+            ix.touch();
+        }
+    }
+}
+
+/// Write the index once the walk is done and changes have settled.
+fn save_if_due() {
+    let (encoded, generation, root) = {
+        let ix = INDEX.lock();
+        if ix.generation == ix.saved_generation
+            || !ix.pending_dirs.is_empty()
+            || !ix.pending_texts.is_empty()
+            || crate::timer::now_ms() < ix.last_change_ms + SAVE_DELAY_MS
+        {
+            return;
+        }
+        (ix.encode(), ix.generation, ix.root)
+    };
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    let result = fat
+        .ensure_subdirectory(root, DIR)
+        .and_then(|dir| fat.write_text_file_in_dir(dir, INDEX_FILE, encoded.as_slice()));
+    let mut ix = INDEX.lock();
+    match result {
+        Ok(()) => ix.saved_generation = generation,
+        Err(err) => {
+            // Read-only or full volume: keep the index in memory and try
+            // again after the next change.
+            ix.saved_generation = generation;
+            crate::println(alloc::format!("search_index: no se pudo guardar: {}", err).as_str());
+        }
+    }
+}
+
+/// Background step, called from the compositor's service loop. Returns
+/// whether any indexing work was done.
+pub fn service() -> bool {
+    {
+        let mut ix = INDEX.lock();
+        let now = crate::timer::now_ms();
+        if now < ix.next_tick_ms {
+            return false;
+        }
+        ix.next_tick_ms = now + TICK_MS;
+    }
+    if !ensure_volume() {
+        return false;
+    }
+    let mut worked = false;
+    for _ in 0..WORK_PER_TICK {
+        match next_job() {
+            Some(Job::Dir(cluster, path)) => scan_dir(cluster, path.as_str()),
+            Some(Job::Text(cluster, size)) => read_terms(cluster, size),
+            None => break,
+        }
+        worked = true;
+    }
+    if !worked {
+        save_if_due();
+    }
+    worked
+}
+
+/// Follow filesystem changes from boot on.
+pub fn init() {
+    crate::fswatch::subscribe(on_fs_event);
+}
+
+/// Queue the folder of a change on the indexed volume for a rescan. Runs on
+/// the writing thread, so it only records the folder.
+fn on_fs_event(event: &crate::fswatch::Event) {
+    let mut guard = INDEX.lock();
+    let ix = &mut *guard;
+    if ix.volume == 0 || event.volume != ix.volume {
+        return;
+    }
+    if ix.dirs.contains_key(&event.dir) && !ix.changed.contains(&event.dir) {
+        ix.changed.push(event.dir);
+    }
+}
+
+/// Walk the whole volume again, ignoring what was indexed.
+pub fn rebuild() -> bool {
+    if !ensure_volume() {
+        return false;
+    }
+    let mut ix = INDEX.lock();
+    let (volume, root) = (ix.volume, ix.root);
+    ix.restart(volume, root);
+    true
+}
+
+/// Files and folders whose name, or whose text, matches `query`; best first.
+pub fn search(query: &str, limit: usize) -> Vec<Hit> {
+    ensure_volume();
+    INDEX.lock().search(query, limit)
+}
+
+/// Settings pages and config keys matching `query`; best first.
+pub fn search_settings(query: &str, limit: usize) -> Vec<SettingHit> {
+    let query = ascii_lower(query.trim());
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let words: Vec<&str> = query.split_whitespace().collect();
+    let mut hits = Vec::new();
+    for (label, keywords, app) in SETTINGS.iter() {
+        let score = score_name(label, query.as_str())
+            .or_else(|| terms_match(keywords, &words).then_some(5_000 - label.len() as i32));
+        if let Some(score) = score {
+            hits.push(SettingHit {
+                label: String::from(*label),
+                detail: String::new(),
+                app,
+                score,
+            });
+        }
+    }
+    for (key, value) in crate::config::list("") {
+        if let Some(score) = score_name(key.as_str(), query.as_str()) {
+            hits.push(SettingHit {
+                detail: alloc::format!("valor: {}", value.to_text()),
+                label: key,
+                app: "settings",
+                score: score - 3_000,
+            });
+        }
+    }
+    hits.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.label.cmp(&b.label)));
+    hits.truncate(limit);
+    hits
+}
+
+/// Whether the first walk of the mounted volume has finished.
+pub fn is_ready() -> bool {
+    ensure_volume() && !INDEX.lock().building
+}
+
+pub fn status() -> Status {
+    let ix = INDEX.lock();
+    Status {
+        records: ix.records.len(),
+        folders: ix.dirs.len(),
+        text_files: ix.records.iter().filter(|r| !r.terms.is_empty()).count(),
+        pending: ix.pending_dirs.len() + ix.pending_texts.len() + ix.changed.len(),
+        building: ix.building,
+        truncated: ix.truncated,
+        unsaved: ix.generation != ix.saved_generation,
+    }
+}
+
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim();
+    let (sub, rest) = args.split_once(' ').unwrap_or((args, ""));
+    let mut out = Vec::new();
+    match sub {
+        "" | "status" => {
+            if !ensure_volume() {
+                out.push(String::from("index: no hay volumen FAT32 montado"));
+                return out;
+            }
+            let st = status();
+            out.push(alloc::format!(
+                "index: {} entradas en {} carpetas, {} archivos de texto con contenido",
+                st.records,
+                st.folders,
+                st.text_files
+            ));
+            out.push(String::from(match (st.building, st.pending) {
+                (true, _) => "estado: indexando por primera vez",
+                (false, 0) => "estado: al dia",
+                (false, _) => "estado: actualizando cambios",
+            }));
+            if st.pending > 0 {
+                out.push(alloc::format!("pendiente: {} tareas", st.pending));
+            }
+            if st.truncated {
+                out.push(alloc::format!("aviso: limite de {} entradas alcanzado", MAX_RECORDS));
+            }
+            if st.unsaved {
+                out.push(alloc::format!("sin guardar todavia en \\{}\\{}", DIR, INDEX_FILE));
+            }
+        }
+        "rebuild" => out.push(String::from(if rebuild() {
+            "index: reconstruyendo en segundo plano"
+        } else {
+            "index: no hay volumen FAT32 montado"
+        })),
+        "find" if !rest.trim().is_empty() => {
+            let hits = search(rest, 20);
+            for setting in search_settings(rest, 5) {
+                if setting.detail.is_empty() {
+                    out.push(alloc::format!("  [ajuste] {}", setting.label));
+                } else {
+                    out.push(alloc::format!("  [ajuste] {} ({})", setting.label, setting.detail));
+                }
+            }
+            for hit in hits.iter() {
+                out.push(alloc::format!(
+                    "  {}{}{}{}",
+                    hit.dir_path,
+                    hit.name,
+                    if hit.is_dir { "/" } else { "" },
+                    if hit.in_contents { "  (contenido)" } else { "" }
+                ));
+            }
+            if out.is_empty() {
+                out.push(alloc::format!("index: sin resultados para '{}'", rest.trim()));
+            }
+            if !is_ready() {
+                out.push(String::from("(el indice aun se esta construyendo)"));
+            }
+        }
+        _ => out.push(String::from(USAGE)),
+    }
+    out
+}
+
+const USAGE: &str = "uso: index [status|rebuild|find <texto>]";
+
+crate::selftest::kernel_tests! {
+    "search_index";
+
+    fn terms_and_scores() {
+        let terms = extract_terms("Hola mundo, hola HOLA 42 año\nmundo_feliz".as_bytes());
+        crate::selftest::ensure_eq(terms.as_str(), "hola mundo año feliz", "palabras")?;
+        crate::selftest::ensure(terms_match(terms.as_str(), &["mun", "ho"]), "prefijos")?;
+        crate::selftest::ensure(!terms_match(terms.as_str(), &["adios"]), "ausente")?;
+        crate::selftest::ensure(!terms_match(terms.as_str(), &["ho"]), "demasiado corta")?;
+        crate::selftest::ensure_eq(score_name("NOTAS.TXT", "notas.txt"), Some(10_000), "exacto")?;
+        crate::selftest::ensure(score_name("NOTAS.TXT", "not") > score_name("MISNOTAS.TXT", "not"), "prefijo")?;
+        crate::selftest::ensure(score_name("INFORME.PDF", "ifp").is_some(), "letras en orden")?;
+        crate::selftest::ensure(score_name("INFORME.PDF", "zz").is_none(), "sin coincidencia")?;
+        crate::selftest::ensure(is_text_name("leeme.MD") && !is_text_name("foto.png"), "extensiones")
+    }
+
+    fn listing_updates_and_round_trip() {
+        let listed = |name: &str, cluster: u32, size: u32, is_dir: bool| Listed {
+            name: String::from(name),
+            cluster,
+            size,
+            is_dir,
+        };
+        let mut ix = Index::new();
+        ix.restart(7, 2);
+        ix.apply_listing(
+            2,
+            "/",
+            alloc::vec![
+                listed("DOCS", 10, 0, true),
+                listed("REDUXOS", 11, 0, true),
+                listed("LEEME.TXT", 20, 100, false),
+            ],
+        );
+        crate::selftest::ensure_eq(ix.records.len(), 2, "REDUXOS omitida")?;
+        crate::selftest::ensure_eq(ix.dirs.get(&10).map(String::as_str), Some("/DOCS/"), "carpeta")?;
+        crate::selftest::ensure_eq(ix.pending_texts.iter().copied().collect::<Vec<_>>(), alloc::vec![20], "texto")?;
+        ix.apply_listing(10, "/DOCS/", alloc::vec![listed("PLAN.MD", 30, 50, false)]);
+        ix.records.iter_mut().find(|r| r.cluster == 30).ok_or("plan")?.terms = String::from("reunion lunes");
+        let hits = ix.search("reun", 5);
+        crate::selftest::ensure_eq(hits.len(), 1, "por contenido")?;
+        crate::selftest::ensure(hits[0].in_contents && hits[0].dir_path == "/DOCS/", "ruta")?;
+
+        let saved = Index::decode(ix.encode().as_slice())?;
+        crate::selftest::ensure_eq(saved.records.clone(), ix.records.clone(), "registros")?;
+        crate::selftest::ensure_eq(saved.dirs.clone(), ix.dirs.clone(), "carpetas")?;
+        let mut broken = ix.encode();
+        broken[10] ^= 1;
+        crate::selftest::ensure(Index::decode(broken.as_slice()).is_err(), "crc")?;
+
+        // Renaming the folder drops its old subtree and queues the new name.
+        ix.apply_listing(2, "/", alloc::vec![listed("PAPELES", 10, 0, true), listed("LEEME.TXT", 20, 100, false)]);
+        crate::selftest::ensure(ix.records.iter().all(|r| r.cluster != 30), "subarbol")?;
+        crate::selftest::ensure_eq(ix.dirs.get(&10).map(String::as_str), Some("/PAPELES/"), "renombrada")
+    }
+
+    fn settings_pages() {
+        let hits = search_settings("brillo", 5);
+        crate::selftest::ensure(hits.first().is_some_and(|h| h.label.starts_with("Color de pantalla")), "palabra clave")?;
+        let hits = search_settings("wifi", 5);
+        crate::selftest::ensure(hits.first().is_some_and(|h| h.app == "wifi"), "pagina antes que ajustes")?;
+        crate::selftest::ensure(search_settings("  ", 5).is_empty(), "consulta vacia")
+    }
+}
@@ -0,0 +1,295 @@
+//! Secure Boot: state detection and MOK enrollment through shim.
+//!
+//! With Secure Boot on, firmware only runs images signed by a key in db.
+//! REDUX64.EFI is not signed by Microsoft, so signed media (`make secureboot`)
+//! boot the distribution's shim as BOOTX64.EFI. Shim then runs GRUBX64.EFI,
+//! our own image signed with the project key, once that key is in the MOK
+//! list. The key's certificate ships as `\EFI\BOOT\REDUX.CER`.
+//!
+//! `request_enrollment` queues the certificate the same way `mokutil
+//! --import` does: `MokNew` holds the signature list and `MokAuth` the
+//! SHA-256 of the list plus the one-time password. On the next boot shim
+//! starts MokManager (MMX64.EFI), which asks for that password before adding
+//! the key.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use uefi::boot;
+use uefi::fs::FileSystem as UefiFileSystem;
+use uefi::runtime::{VariableAttributes, VariableVendor};
+use uefi::{cstr16, CStr16, Guid, Status};
+
+/// Vendor GUID of shim's variables (MokNew, MokListRT, ...).
+const SHIM_LOCK_GUID: Guid = uefi::guid!("605dab50-e046-4300-abb6-3dd810dd8b23");
+const EFI_CERT_X509_GUID: Guid = uefi::guid!("a5c059a1-94e4-4aa7-87b5-ab155c2bf072");
+/// EFI_SIGNATURE_LIST header: type GUID plus three u32 sizes.
+const SIGNATURE_LIST_HEADER: usize = 16 + 4 * 3;
+/// MokManager accepts passwords of 1 to 16 characters.
+pub const PASSWORD_DIGITS: usize = 8;
+const CERT_MAX_BYTES: usize = 16 * 1024;
+const EFI_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct State {
+    /// Firmware enforces signatures (`SecureBoot` = 1).
+    pub enabled: bool,
+    /// No platform key yet; anything boots and keys can be set freely.
+    pub setup_mode: bool,
+    /// Shim ran before us and left `MokListRT` behind.
+    pub shim: bool,
+}
+
+pub fn state() -> State {
+    let global = VariableVendor::GLOBAL_VARIABLE;
+    State {
+        enabled: read_flag(cstr16!("SecureBoot"), &global),
+        setup_mode: read_flag(cstr16!("SetupMode"), &global),
+        shim: read_variable(cstr16!("MokListRT"), &VariableVendor(SHIM_LOCK_GUID)).is_some(),
+    }
+}
+
+fn read_variable(name: &CStr16, vendor: &VariableVendor) -> Option<Vec<u8>> {
+    uefi::runtime::get_variable_boxed(name, vendor)
+        .ok()
+        .map(|(bytes, _attrs)| bytes.to_vec())
+}
+
+fn read_flag(name: &CStr16, vendor: &VariableVendor) -> bool {
+    read_variable(name, vendor)
+        .map(|bytes| bytes.first() == Some(&1))
+        .unwrap_or(false)
+}
+
+/// Files the installer copies to keep a Secure Boot machine bootable.
+pub struct ShimAssets {
+    pub shim: Vec<u8>,
+    pub mok_manager: Vec<u8>,
+    /// DER certificate of the project signing key.
+    pub certificate: Vec<u8>,
+    /// REDUX64.EFI as signed on the media. The copy rebuilt from memory has
+    /// lost its signature.
+    pub signed_payload: Vec<u8>,
+}
+
+fn is_pe(bytes: &[u8]) -> bool {
+    bytes.len() >= 4096 && bytes.len() <= EFI_MAX_BYTES && bytes.starts_with(b"MZ")
+}
+
+/// Shim, MokManager, certificate and signed image from the boot media; `None`
+/// unless all four are there.
+pub fn load_assets() -> Option<ShimAssets> {
+    let fs_proto = boot::get_image_file_system(boot::image_handle()).ok()?;
+    let mut fs = UefiFileSystem::new(fs_proto);
+    let shim = fs.read(cstr16!("\\EFI\\BOOT\\SHIMX64.EFI")).ok().filter(|b| is_pe(b))?;
+    let mok_manager = fs.read(cstr16!("\\EFI\\BOOT\\MMX64.EFI")).ok().filter(|b| is_pe(b))?;
+    let signed_payload = fs.read(cstr16!("\\EFI\\BOOT\\REDUX64.EFI")).ok().filter(|b| is_pe(b))?;
+    let certificate = fs
+        .read(cstr16!("\\EFI\\BOOT\\REDUX.CER"))
+        .ok()
+        .filter(|b| !b.is_empty() && b.len() <= CERT_MAX_BYTES && b[0] == 0x30)?;
+    Some(ShimAssets {
+        shim,
+        mok_manager,
+        certificate,
+        signed_payload,
+    })
+}
+
+/// EFI_SIGNATURE_LIST holding one X.509 certificate owned by shim.
+fn signature_list(cert: &[u8]) -> Vec<u8> {
+    let signature_size = 16 + cert.len();
+    let list_size = SIGNATURE_LIST_HEADER + signature_size;
+    let mut out = Vec::with_capacity(list_size);
+    out.extend_from_slice(&EFI_CERT_X509_GUID.to_bytes());
+    out.extend_from_slice(&(list_size as u32).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&(signature_size as u32).to_le_bytes());
+    out.extend_from_slice(&SHIM_LOCK_GUID.to_bytes());
+    out.extend_from_slice(cert);
+    out
+}
+
+/// SHA-256 of the list followed by the password in UCS-2, as MokManager
+/// checks it.
+fn auth_hash(list: &[u8], password: &str) -> [u8; 32] {
+    let mut hasher = crate::crypto::Sha256::new();
+    hasher.update(list);
+    for unit in password.encode_utf16() {
+        hasher.update(&unit.to_le_bytes());
+    }
+    hasher.finish()
+}
+
+/// Whether MokListRT already holds `cert`.
+pub fn certificate_enrolled(cert: &[u8]) -> bool {
+    match read_variable(cstr16!("MokListRT"), &VariableVendor(SHIM_LOCK_GUID)) {
+        Some(list) => !cert.is_empty() && list.windows(cert.len()).any(|window| window == cert),
+        None => false,
+    }
+}
+
+/// An enrollment is waiting for MokManager.
+pub fn enrollment_pending() -> bool {
+    read_variable(cstr16!("MokNew"), &VariableVendor(SHIM_LOCK_GUID)).is_some()
+}
+
+/// Random numeric one-time password for MokManager.
+pub fn new_password() -> String {
+    (0..PASSWORD_DIGITS)
+        .map(|_| char::from(b'0' + (crate::random::next_u32() % 10) as u8))
+        .collect()
+}
+
+/// Queue `cert` for MokManager on the next boot, protected by `password`.
+pub fn request_enrollment(cert: &[u8], password: &str) -> Result<(), String> {
+    if cert.is_empty() || cert.len() > CERT_MAX_BYTES {
+        return Err(String::from("certificado invalido"));
+    }
+    if password.is_empty() || password.chars().count() > 16 {
+        return Err(String::from("la contrasena debe tener de 1 a 16 caracteres"));
+    }
+    let vendor = VariableVendor(SHIM_LOCK_GUID);
+    let attrs =
+        VariableAttributes::NON_VOLATILE | VariableAttributes::BOOTSERVICE_ACCESS | VariableAttributes::RUNTIME_ACCESS;
+    let list = signature_list(cert);
+    let auth = auth_hash(&list, password);
+    uefi::runtime::set_variable(cstr16!("MokNew"), &vendor, attrs, &list)
+        .map_err(|err| alloc::format!("escribiendo MokNew: {:?}", err.status()))?;
+    uefi::runtime::set_variable(cstr16!("MokAuth"), &vendor, attrs, &auth).map_err(|err| {
+        let _ = uefi::runtime::delete_variable(cstr16!("MokNew"), &vendor);
+        alloc::format!("escribiendo MokAuth: {:?}", err.status())
+    })
+}
+
+/// Drop a pending enrollment.
+pub fn cancel_enrollment() -> Result<(), String> {
+    let vendor = VariableVendor(SHIM_LOCK_GUID);
+    for name in [cstr16!("MokNew"), cstr16!("MokAuth")] {
+        match uefi::runtime::delete_variable(name, &vendor) {
+            Ok(()) => {}
+            Err(err) if err.status() == Status::NOT_FOUND => {}
+            Err(err) => return Err(alloc::format!("borrando {}: {:?}", name, err.status())),
+        }
+    }
+    Ok(())
+}
+
+/// Steps MokManager walks through once the enrollment is queued.
+pub fn guidance_lines(password: &str) -> Vec<String> {
+    alloc::vec![
+        String::from("1. Reinicia. Shim abre MokManager (pantalla azul) antes de Zenox OS."),
+        String::from("2. Pulsa una tecla antes de 10 segundos y elige \"Enroll MOK\"."),
+        String::from("3. \"View key 0\" muestra el certificado de Zenox OS; despues \"Continue\" y \"Yes\"."),
+        alloc::format!("4. Escribe la contrasena de un solo uso: {}", password),
+        String::from("5. Elige \"Reboot\". A partir de ahi el sistema arranca con Secure Boot activo."),
+    ]
+}
+
+fn read_certificate() -> Result<Vec<u8>, String> {
+    let fs_proto = boot::get_image_file_system(boot::image_handle())
+        .map_err(|_| String::from("sin sistema de archivos de arranque"))?;
+    let mut fs = UefiFileSystem::new(fs_proto);
+    fs.read(cstr16!("\\EFI\\BOOT\\REDUX.CER"))
+        .map_err(|_| String::from("no se encontro \\EFI\\BOOT\\REDUX.CER"))
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "si"
+    } else {
+        "no"
+    }
+}
+
+pub fn status_lines() -> Vec<String> {
+    let state = state();
+    let mut lines = alloc::vec![alloc::format!(
+        "Secure Boot {}, modo setup {}, shim {}",
+        if state.enabled { "activo" } else { "inactivo" },
+        yes_no(state.setup_mode),
+        yes_no(state.shim)
+    )];
+    match read_certificate() {
+        Ok(cert) => lines.push(alloc::format!(
+            "Certificado REDUX.CER ({} bytes): {}",
+            cert.len(),
+            if certificate_enrolled(&cert) {
+                "en la lista MOK"
+            } else if enrollment_pending() {
+                "pendiente de MokManager"
+            } else {
+                "sin inscribir"
+            }
+        )),
+        Err(err) => lines.push(err),
+    }
+    if state.enabled && !state.shim {
+        lines.push(String::from(
+            "Arranque firmado directamente por db; shim no hace falta.",
+        ));
+    }
+    lines
+}
+
+/// `secureboot [status]`, `secureboot enroll` (queues REDUX.CER with a new
+/// password) and `secureboot cancel`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    match args.trim() {
+        "" | "status" => status_lines(),
+        "enroll" => {
+            let cert = match read_certificate() {
+                Ok(cert) => cert,
+                Err(err) => return alloc::vec![err],
+            };
+            if certificate_enrolled(&cert) {
+                return alloc::vec![String::from("El certificado ya esta en la lista MOK.")];
+            }
+            let password = new_password();
+            match request_enrollment(&cert, &password) {
+                Ok(()) => {
+                    let mut lines = alloc::vec![String::from("Inscripcion preparada para el proximo arranque:")];
+                    lines.extend(guidance_lines(&password));
+                    lines
+                }
+                Err(err) => alloc::vec![alloc::format!("secureboot: {}", err)],
+            }
+        }
+        "cancel" => match cancel_enrollment() {
+            Ok(()) => alloc::vec![String::from("Inscripcion MOK cancelada.")],
+            Err(err) => alloc::vec![alloc::format!("secureboot: {}", err)],
+        },
+        _ => alloc::vec![String::from("Uso: secureboot [status|enroll|cancel]")],
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "secureboot";
+
+    fn signature_list_layout() {
+        let cert = [0x30u8, 0x82, 0x01, 0x02, 0xAA];
+        let list = signature_list(&cert);
+        crate::selftest::ensure_eq(list.len(), SIGNATURE_LIST_HEADER + 16 + cert.len(), "tamano")?;
+        crate::selftest::ensure_eq(&list[..16], EFI_CERT_X509_GUID.to_bytes().as_slice(), "tipo")?;
+        crate::selftest::ensure_eq(&list[16..20], (list.len() as u32).to_le_bytes().as_slice(), "tamano de lista")?;
+        crate::selftest::ensure_eq(&list[20..24], [0u8; 4].as_slice(), "cabecera")?;
+        crate::selftest::ensure_eq(&list[24..28], 21u32.to_le_bytes().as_slice(), "tamano de firma")?;
+        crate::selftest::ensure_eq(&list[28..44], SHIM_LOCK_GUID.to_bytes().as_slice(), "propietario")?;
+        crate::selftest::ensure_eq(&list[44..], cert.as_slice(), "certificado")
+    }
+
+    fn auth_hashes_ucs2_password() {
+        let list = signature_list(&[0x30, 0x00]);
+        let mut expected = crate::crypto::Sha256::new();
+        expected.update(&list);
+        expected.update(&[b'4', 0, b'2', 0]);
+        crate::selftest::ensure_eq(auth_hash(&list, "42"), expected.finish(), "MokAuth")?;
+        crate::selftest::ensure(auth_hash(&list, "42") != auth_hash(&list, "43"), "misma huella")
+    }
+
+    fn passwords_are_digits() {
+        let password = new_password();
+        crate::selftest::ensure_eq(password.len(), PASSWORD_DIGITS, "longitud")?;
+        crate::selftest::ensure(password.bytes().all(|b| b.is_ascii_digit()), "solo digitos")
+    }
+}
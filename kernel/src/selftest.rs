@@ -0,0 +1,239 @@
+//! In-kernel test registry.
+//!
+//! Modules declare their checks with `kernel_tests!`, which only compiles them
+//! when the `selftest` cargo feature is enabled:
+//!
+//! ```ignore
+//! crate::selftest::kernel_tests! {
+//!     "memory";
+//!     fn heap_roundtrip() { crate::selftest::ensure(true, "heap") }
+//! }
+//! ```
+//!
+//! Each block expands to a `selftests` child module with a `TESTS` table; the
+//! tables are listed in `REGISTRY` below. The `selftest` shell command and the
+//! QEMU harness (`SELFTEST` verb or the `selftest` boot option) run them.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub type TestResult = Result<(), String>;
+
+pub struct KernelTest {
+    pub suite: &'static str,
+    pub name: &'static str,
+    pub run: fn() -> TestResult,
+}
+
+macro_rules! kernel_tests {
+    ($suite:literal; $(fn $name:ident() $body:block)*) => {
+        #[cfg(feature = "selftest")]
+        pub(crate) mod selftests {
+            #[allow(unused_imports)]
+            use super::*;
+
+            $(fn $name() -> $crate::selftest::TestResult $body)*
+
+            pub(crate) const TESTS: &[$crate::selftest::KernelTest] = &[
+                $($crate::selftest::KernelTest { suite: $suite, name: stringify!($name), run: $name },)*
+            ];
+        }
+    };
+}
+pub(crate) use kernel_tests;
+
+#[cfg(feature = "selftest")]
+const REGISTRY: &[&[KernelTest]] = &[
+    crate::memory::selftests::TESTS,
+    crate::scheduler::selftests::TESTS,
+    crate::fat32::selftests::TESTS,
+    crate::net::selftests::TESTS,
+    crate::compress::selftests::TESTS,
+    crate::archive::selftests::TESTS,
+    crate::pkg::selftests::TESTS,
+    crate::gui::notifications::selftests::TESTS,
+    crate::config::selftests::TESTS,
+    crate::i18n::selftests::TESTS,
+    crate::gui::hidpi::selftests::TESTS,
+    crate::gamepad::selftests::TESTS,
+    crate::perf::selftests::TESTS,
+    crate::crypto::selftests::TESTS,
+    crate::random::selftests::TESTS,
+    crate::aslr::selftests::TESTS,
+    crate::stack_guard::selftests::TESTS,
+    crate::wx::selftests::TESTS,
+    crate::uaccess::selftests::TESTS,
+    crate::secureboot::selftests::TESTS,
+    crate::tpm::selftests::TESTS,
+    crate::crypt::selftests::TESTS,
+    crate::bootvar::selftests::TESTS,
+    crate::osprober::selftests::TESTS,
+    crate::linuxboot::selftests::TESTS,
+    crate::checkpoint::selftests::TESTS,
+    crate::kmod::selftests::TESTS,
+    crate::device::selftests::TESTS,
+    crate::sysfs::selftests::TESTS,
+    crate::fs::vfs::selftests::TESTS,
+    crate::sync::selftests::TESTS,
+    crate::timer::selftests::TESTS,
+    crate::clocksource::selftests::TESTS,
+    crate::executor::selftests::TESTS,
+    crate::klog::selftests::TESTS,
+    crate::i18n::format::selftests::TESTS,
+    crate::fbcon::selftests::TESTS,
+    crate::procenv::selftests::TESTS,
+    crate::gui::osk::selftests::TESTS,
+    crate::gui::effects::selftests::TESTS,
+    crate::gui::session::selftests::TESTS,
+    crate::touch::selftests::TESTS,
+    crate::gamma::selftests::TESTS,
+    crate::print::selftests::TESTS,
+    crate::ttf::selftests::TESTS,
+    crate::pdf::selftests::TESTS,
+    crate::calc::selftests::TESTS,
+    crate::calendar::selftests::TESTS,
+    crate::alarm::selftests::TESTS,
+    crate::capture::selftests::TESTS,
+    crate::synth::selftests::TESTS,
+    crate::xhci::selftests::TESTS,
+    crate::usb::selftests::TESTS,
+    crate::thunderbolt::selftests::TESTS,
+    crate::acpi::selftests::TESTS,
+    crate::pm::selftests::TESTS,
+    crate::cmdline::selftests::TESTS,
+    crate::safemode::selftests::TESTS,
+    crate::driver_guard::selftests::TESTS,
+    crate::search_index::selftests::TESTS,
+    crate::gui::search_overlay::selftests::TESTS,
+    crate::fswatch::selftests::TESTS,
+    crate::fsmeta::selftests::TESTS,
+    crate::perm::selftests::TESTS,
+    crate::fsjournal::selftests::TESTS,
+    crate::chkdsk::selftests::TESTS,
+    crate::bootmenu::selftests::TESTS,
+    crate::recovery::selftests::TESTS,
+    crate::heapcheck::selftests::TESTS,
+    crate::replay::selftests::TESTS,
+    crate::bench::selftests::TESTS,
+    crate::gui::event_queue::selftests::TESTS,
+    crate::gui::clipboard::selftests::TESTS,
+    crate::gui::interaction::selftests::TESTS,
+    crate::gui::dialog::selftests::TESTS,
+    crate::gui::screenshot::selftests::TESTS,
+    crate::gui::widgets::list::selftests::TESTS,
+    crate::gui::widgets::text_area::selftests::TESTS,
+    crate::process::selftests::TESTS,
+    crate::sysmon::selftests::TESTS,
+    crate::trace::selftests::TESTS,
+    crate::boottime::selftests::TESTS,
+    crate::net::bench::selftests::TESTS,
+    crate::net::redux_http::selftests::TESTS,
+    crate::net::request::selftests::TESTS,
+    crate::net::pool::selftests::TESTS,
+    crate::net::worker::selftests::TESTS,
+    crate::net::dhcp::selftests::TESTS,
+    crate::net::profile::selftests::TESTS,
+    crate::net::portal::selftests::TESTS,
+    crate::net::stats::selftests::TESTS,
+    crate::net::arp::selftests::TESTS,
+    crate::net::mtu::selftests::TESTS,
+    crate::net::mdns::selftests::TESTS,
+    crate::net::ipp::selftests::TESTS,
+    crate::net::remote::selftests::TESTS,
+];
+
+#[cfg(not(feature = "selftest"))]
+const REGISTRY: &[&[KernelTest]] = &[];
+
+/// `Ok(())` when `cond` holds, otherwise an error naming the failed check.
+pub fn ensure(cond: bool, what: &str) -> TestResult {
+    if cond {
+        Ok(())
+    } else {
+        Err(alloc::format!("fallo: {}", what))
+    }
+}
+
+pub fn ensure_eq<T: PartialEq + core::fmt::Debug>(left: T, right: T, what: &str) -> TestResult {
+    if left == right {
+        Ok(())
+    } else {
+        Err(alloc::format!("{}: {:?} != {:?}", what, left, right))
+    }
+}
+
+pub fn tests() -> impl Iterator<Item = &'static KernelTest> {
+    REGISTRY.iter().flat_map(|suite| suite.iter())
+}
+
+pub struct SelftestOutcome {
+    pub test: &'static KernelTest,
+    pub result: TestResult,
+}
+
+/// Run every registered test whose suite or `suite::name` starts with `filter`.
+pub fn run(filter: &str) -> Vec<SelftestOutcome> {
+    let mut out = Vec::new();
+    for test in tests() {
+        if !filter.is_empty() {
+            let full = alloc::format!("{}::{}", test.suite, test.name);
+            if !full.starts_with(filter) {
+                continue;
+            }
+        }
+        let result = (test.run)();
+        if crate::testharness::is_enabled() {
+            let detail = match result.as_ref() {
+                Ok(()) => String::new(),
+                Err(e) => e.clone(),
+            };
+            crate::testharness::report_test(
+                alloc::format!("{}::{}", test.suite, test.name).as_str(),
+                result.is_ok(),
+                detail.as_str(),
+            );
+        }
+        out.push(SelftestOutcome { test, result });
+    }
+    out
+}
+
+/// Shared implementation of `selftest [list|<suite>[::test]]`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim();
+    let mut out = Vec::new();
+    if !cfg!(feature = "selftest") {
+        out.push(String::from("selftest: no compilado (cargo build --features selftest)."));
+        return out;
+    }
+    if args == "list" {
+        for test in tests() {
+            out.push(alloc::format!("  {}::{}", test.suite, test.name));
+        }
+        return out;
+    }
+
+    let outcomes = run(args);
+    let mut failed = 0usize;
+    for outcome in outcomes.iter() {
+        match outcome.result.as_ref() {
+            Ok(()) => out.push(alloc::format!("  [ OK ] {}::{}", outcome.test.suite, outcome.test.name)),
+            Err(e) => {
+                failed += 1;
+                out.push(alloc::format!(
+                    "  [FAIL] {}::{} - {}",
+                    outcome.test.suite,
+                    outcome.test.name,
+                    e
+                ));
+            }
+        }
+    }
+    out.push(alloc::format!(
+        "selftest: {} ejecutados, {} correctos, fallidos={}",
+        outcomes.len(),
+        outcomes.len() - failed,
+        failed
+    ));
+    out
+}
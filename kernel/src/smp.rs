@@ -10,6 +10,7 @@ use sha2::{Digest, Sha256};
 use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use core::arch::{asm, global_asm};
 use alloc::{boxed::Box, string::String, vec::Vec};
+use crate::stack_guard::GuardedStack;
 
 // ---------------------------------------------------------------------------
 // AP trampoline (baremetal)
@@ -78,16 +79,25 @@ ap_trampoline_prot:
     mov cr3, eax
 
     mov eax, cr4
-    or eax, 0x20
+    or eax, 0x620
     mov cr4, eax
 
+    // EFER.LME, plus EFER.NXE when CPUID 0x80000001 EDX[20] reports NX:
+    // the page tables carry NX bits once `wx` has run.
+    mov eax, 0x80000001
+    cpuid
+    and edx, 0x100000
+    shr edx, 9
+    mov esi, edx
     mov ecx, 0xC0000080
     rdmsr
     or eax, 0x100
+    or eax, esi
     wrmsr
 
+    // PG and WP.
     mov eax, cr0
-    or eax, 0x80000000
+    or eax, 0x80010000
     mov cr0, eax
 
     push 0x18
@@ -684,12 +694,11 @@ impl ApTss {
 struct ApBootState {
     gdt: [u64; AP_GDT_LEN],
     tss: ApTss,
-    stack: [u8; AP_STACK_SIZE],
 }
 
 impl ApBootState {
     const fn new() -> Self {
-        Self { gdt: [0u64; AP_GDT_LEN], tss: ApTss::new(), stack: [0u8; AP_STACK_SIZE] }
+        Self { gdt: [0u64; AP_GDT_LEN], tss: ApTss::new() }
     }
 }
 
@@ -698,6 +707,23 @@ static mut AP_BOOT_STATES: [ApBootState; MAX_BOOT_APS] = {
     [INIT; MAX_BOOT_APS]
 };
 
+// Kept apart from AP_BOOT_STATES so each stack gets its own guard page.
+static mut AP_STACKS: [GuardedStack<AP_STACK_SIZE>; MAX_BOOT_APS] = {
+    const INIT: GuardedStack<AP_STACK_SIZE> = GuardedStack::new();
+    [INIT; MAX_BOOT_APS]
+};
+
+fn ap_stack_top(ap_index: usize) -> u64 {
+    unsafe { (*core::ptr::addr_of!(AP_STACKS[ap_index])).top() }
+}
+
+pub fn register_stack_guards() {
+    for index in 0..MAX_BOOT_APS {
+        let base = unsafe { (*core::ptr::addr_of!(AP_STACKS[index])).guard_base() };
+        crate::stack_guard::register(base, crate::stack_guard::Owner::Ap(index));
+    }
+}
+
 const fn ap_tss_descriptor(base: u64, limit: u32) -> (u64, u64) {
     let low = (limit as u64 & 0xFFFF)
         | ((base & 0xFFFF) << 16)
@@ -713,9 +739,12 @@ fn init_ap_boot_state(ap_index: usize) {
     if ap_index >= MAX_BOOT_APS { return; }
     unsafe {
         let state = &mut AP_BOOT_STATES[ap_index];
-        let stack_top = (core::ptr::addr_of!(state.stack) as *const u8 as u64)
-            + AP_STACK_SIZE as u64;
-        state.tss.rsp[0] = stack_top;
+        state.tss.rsp[0] = ap_stack_top(ap_index);
+        if state.tss.ist[0] == 0 {
+            // Double-fault stack (IST 1); kept across re-inits.
+            let stack = alloc::vec![0u8; crate::stack_guard::DOUBLE_FAULT_STACK_SIZE].leak();
+            state.tss.ist[0] = (stack.as_ptr() as u64 + stack.len() as u64) & !0xF;
+        }
         state.tss.iomap_base = core::mem::size_of::<ApTss>() as u16;
 
         state.gdt[0] = 0;
@@ -826,6 +855,7 @@ extern "C" fn ap_entry_baremetal() -> ! {
     let _ = enable_local_apic();
     ap_init_lapic();
     crate::interrupts::load_current_idt();
+    crate::uaccess::enable_on_this_cpu();
 
     mark_ap_online(apic_id);
     APS_ONLINE.fetch_add(1, Ordering::SeqCst);
@@ -900,8 +930,7 @@ fn bootstrap_aps_baremetal() -> u32 {
         init_ap_boot_state(ap_index);
         unsafe {
             let state = &AP_BOOT_STATES[ap_index];
-            let stack_top = (core::ptr::addr_of!(state.stack) as *const u8 as u64)
-                + AP_STACK_SIZE as u64;
+            let stack_top = ap_stack_top(ap_index);
             let gdt_ptr = ApGdtPointer {
                 limit: (core::mem::size_of::<[u64; AP_GDT_LEN]>() - 1) as u16,
                 base: (core::ptr::addr_of!(state.gdt) as *const _) as u64,
@@ -993,6 +1022,20 @@ fn bootstrap_aps_uefi() -> u32 {
     APS_ONLINE.load(Ordering::SeqCst)
 }
 
+/// Run `procedure` once on every AP the firmware manages, for per-CPU state
+/// that must be in place before the BSP relies on it. False without
+/// MpServices.
+pub fn run_on_firmware_aps(procedure: extern "efiapi" fn(*mut core::ffi::c_void)) -> bool {
+    let Ok(handle) = uefi::boot::get_handle_for_protocol::<uefi::proto::pi::mp::MpServices>() else {
+        return false;
+    };
+    let Ok(mp) = uefi::boot::open_protocol_exclusive::<uefi::proto::pi::mp::MpServices>(handle) else {
+        return false;
+    };
+    let timeout = Some(core::time::Duration::from_secs(5));
+    mp.startup_all_aps(true, procedure, core::ptr::null_mut(), None, timeout).is_ok()
+}
+
 /// Bootstrap all APs. Uses UEFI MpServices in firmware mode, falls back to
 /// baremetal INIT/SIPI trampoline after ExitBootServices.
 pub fn bootstrap_aps() -> u32 {
@@ -78,6 +78,16 @@ impl<T> SpinLock<T> {
             }
         }
     }
+
+    /// Release the lock without a guard, for a holder that will never drop
+    /// its own: a driver `driver_guard` abandoned mid-call.
+    ///
+    /// # Safety
+    /// Nobody may still be using the data under the abandoned guard.
+    pub unsafe fn force_unlock(&self) {
+        let next = self.next_ticket.load(Ordering::Relaxed);
+        self.now_serving.store(next, Ordering::Release);
+    }
 }
 
 /// RAII guard for SpinLock. Releases the lock and restores interrupts on drop.
@@ -115,7 +125,7 @@ impl<T> Drop for SpinLockGuard<'_, T> {
 
 /// Save RFLAGS and disable interrupts (cli).
 #[inline(always)]
-fn save_and_disable_interrupts() -> u64 {
+pub(crate) fn save_and_disable_interrupts() -> u64 {
     let rflags: u64;
     unsafe {
         core::arch::asm!(
@@ -131,7 +141,7 @@ fn save_and_disable_interrupts() -> u64 {
 
 /// Restore RFLAGS (re-enables interrupts if they were enabled before).
 #[inline(always)]
-fn restore_interrupts(rflags: u64) {
+pub(crate) fn restore_interrupts(rflags: u64) {
     if rflags & 0x200 != 0 {
         // IF was set — re-enable interrupts
         unsafe { core::arch::asm!("sti", options(nomem, nostack)); }
@@ -0,0 +1,180 @@
+//! Guard pages below kernel stacks.
+//!
+//! Kernel thread stacks, the ring-transition stack and the AP boot stacks are
+//! `GuardedStack`s: one page-aligned page sits below the stack, and `init`
+//! removes it from the kernel page tables. A stack that grows into it faults
+//! instead of overwriting the next stack. The fault cannot be delivered on a
+//! stack that is already full, so the CPU escalates it to a double fault. That
+//! fault runs on its own IST stack and `interrupts` panics with "stack overflow
+//! in task X", naming the owner found here.
+//!
+//! The BSP's boot stack belongs to the firmware and has no guard.
+
+use alloc::string::String;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::println;
+
+pub const GUARD_SIZE: usize = 4096;
+/// Stack the double-fault handler runs on (IST 1).
+pub const DOUBLE_FAULT_STACK_SIZE: usize = 16 * 1024;
+const MAX_GUARDS: usize = 160;
+
+/// A stack with one guard page below it.
+#[repr(C, align(4096))]
+pub struct GuardedStack<const N: usize> {
+    guard: [u8; GUARD_SIZE],
+    stack: [u8; N],
+}
+
+impl<const N: usize> GuardedStack<N> {
+    pub const fn new() -> Self {
+        Self {
+            guard: [0; GUARD_SIZE],
+            stack: [0; N],
+        }
+    }
+
+    pub fn guard_base(&self) -> u64 {
+        self.guard.as_ptr() as u64
+    }
+
+    pub fn bottom(&self) -> u64 {
+        self.stack.as_ptr() as u64
+    }
+
+    pub fn top(&self) -> u64 {
+        self.bottom() + N as u64
+    }
+}
+
+/// Whose stack a guard page protects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Owner {
+    /// Kernel thread slot in `process`.
+    Thread(usize),
+    /// Stack the CPU switches to on syscalls and interrupts from ring 3.
+    RingTransition,
+    /// Boot stack of the AP with this index.
+    Ap(usize),
+    DoubleFault,
+}
+
+#[derive(Clone, Copy)]
+struct Guard {
+    base: u64,
+    owner: Owner,
+}
+
+static mut GUARDS: [Guard; MAX_GUARDS] = [Guard {
+    base: 0,
+    owner: Owner::DoubleFault,
+}; MAX_GUARDS];
+static GUARD_COUNT: AtomicUsize = AtomicUsize::new(0);
+static ARMED: AtomicBool = AtomicBool::new(false);
+static mut DOUBLE_FAULT_STACK: GuardedStack<DOUBLE_FAULT_STACK_SIZE> = GuardedStack::new();
+
+/// Record the guard page at `base`. After `init` the page is unmapped at
+/// once; before, `init` unmaps everything registered so far.
+pub fn register(base: u64, owner: Owner) {
+    let index = GUARD_COUNT.load(Ordering::Acquire);
+    if index >= MAX_GUARDS || owner_at(base).is_some() {
+        return;
+    }
+    unsafe {
+        GUARDS[index] = Guard { base, owner };
+    }
+    GUARD_COUNT.store(index + 1, Ordering::Release);
+    if ARMED.load(Ordering::Acquire) {
+        let _ = crate::paging::unmap_kernel_page(base);
+    }
+}
+
+/// Owner of the guard page containing `addr`.
+pub fn owner_at(addr: u64) -> Option<Owner> {
+    let count = GUARD_COUNT.load(Ordering::Acquire);
+    let guards = unsafe { &*core::ptr::addr_of!(GUARDS) };
+    guards[..count]
+        .iter()
+        .find(|guard| addr >= guard.base && addr < guard.base + GUARD_SIZE as u64)
+        .map(|guard| guard.owner)
+}
+
+/// Top of the BSP's double-fault stack.
+pub fn double_fault_stack_top() -> u64 {
+    unsafe { (*core::ptr::addr_of!(DOUBLE_FAULT_STACK)).top() }
+}
+
+/// "task X" in the overflow panic.
+pub fn owner_label(owner: Owner) -> String {
+    match owner {
+        Owner::Thread(index) => match crate::process::thread_info_for_fault(index) {
+            Some(info) => alloc::format!(
+                "tid {} ({})",
+                info.tid,
+                core::str::from_utf8(&info.name[..info.name_len as usize]).unwrap_or("?")
+            ),
+            None => alloc::format!("thread slot {}", index),
+        },
+        Owner::RingTransition => String::from("ring 0 entry stack"),
+        Owner::Ap(index) => alloc::format!("AP {} boot stack", index),
+        Owner::DoubleFault => String::from("double fault handler"),
+    }
+}
+
+/// Unmap every registered guard page and give the BSP its double-fault
+/// stack. Runs before the APs start.
+pub fn init() {
+    unsafe {
+        let stack = &*core::ptr::addr_of!(DOUBLE_FAULT_STACK);
+        register(stack.guard_base(), Owner::DoubleFault);
+    }
+    crate::process::register_stack_guards();
+    crate::privilege::register_stack_guard();
+    crate::smp::register_stack_guards();
+
+    let count = GUARD_COUNT.load(Ordering::Acquire);
+    let mut failed = 0usize;
+    for index in 0..count {
+        let base = unsafe { GUARDS[index].base };
+        if crate::paging::unmap_kernel_page(base).is_err() {
+            failed += 1;
+        }
+    }
+    ARMED.store(true, Ordering::Release);
+    crate::privilege::load_task_register_uefi(double_fault_stack_top());
+    crate::interrupts::use_double_fault_stack();
+    if failed == 0 {
+        println(alloc::format!("Stack guard: {} paginas de guarda", count).as_str());
+    } else {
+        println(alloc::format!("Stack guard: {} de {} paginas sin quitar del mapa", failed, count).as_str());
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "stack_guard";
+
+    fn guards_are_unmapped() {
+        let count = GUARD_COUNT.load(Ordering::Acquire);
+        crate::selftest::ensure(count > 0, "sin paginas de guarda")?;
+        for index in 0..count {
+            let guard = unsafe { GUARDS[index] };
+            crate::selftest::ensure(!crate::paging::kernel_page_mapped(guard.base), "guarda con mapa")?;
+            crate::selftest::ensure(
+                crate::paging::kernel_page_mapped(guard.base + GUARD_SIZE as u64),
+                "pila sin mapa",
+            )?;
+        }
+        Ok(())
+    }
+
+    fn owner_lookup() {
+        let top = double_fault_stack_top();
+        let base = top - (DOUBLE_FAULT_STACK_SIZE + GUARD_SIZE) as u64;
+        crate::selftest::ensure_eq(owner_at(base), Some(Owner::DoubleFault), "inicio de la guarda")?;
+        crate::selftest::ensure_eq(owner_at(base + GUARD_SIZE as u64 - 1), Some(Owner::DoubleFault), "fin")?;
+        crate::selftest::ensure_eq(owner_at(base + GUARD_SIZE as u64), None, "primer byte de la pila")?;
+        Ok(())
+    }
+
+}
@@ -0,0 +1,173 @@
+//! Cell types for kernel globals.
+//!
+//! New globals should not be `static mut`: every access hands out a fresh
+//! aliasing reference and nothing stops two cores from racing on it. Use:
+//!
+//! - [`SpinLock`](crate::spinlock::SpinLock) for state reached from more than
+//!   one context. It masks interrupts while held, so IRQ handlers may take it.
+//! - [`Once`] for values written once during init and only read afterwards.
+//! - Atomics for counters, flags and small scalars.
+//! - [`KernelCell`] for state that only the kernel thread touches and that is
+//!   borrowed across long call chains (the FAT volume, the smoltcp stack),
+//!   where a lock would deadlock on re-entry. The `unsafe` stays at each call
+//!   site and the rule it relies on is written down once, here.
+
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+const EMPTY: u8 = 0;
+const BUSY: u8 = 1;
+const READY: u8 = 2;
+
+/// A value set at most once, then shared read-only (`OnceCell` for the kernel).
+pub struct Once<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// SAFETY: the value is written once before READY is published and only read after.
+unsafe impl<T: Send + Sync> Sync for Once<T> {}
+unsafe impl<T: Send> Send for Once<T> {}
+
+impl<T> Once<T> {
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(EMPTY),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    pub fn get(&self) -> Option<&T> {
+        if self.state.load(Ordering::Acquire) == READY {
+            Some(unsafe { (*self.value.get()).assume_init_ref() })
+        } else {
+            None
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.state.load(Ordering::Acquire) == READY
+    }
+
+    /// Store `value` unless the cell is already set, in which case it is
+    /// handed back.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        if self
+            .state
+            .compare_exchange(EMPTY, BUSY, Ordering::Acquire, Ordering::Acquire)
+            .is_err()
+        {
+            return Err(value);
+        }
+        unsafe {
+            (*self.value.get()).write(value);
+        }
+        self.state.store(READY, Ordering::Release);
+        Ok(())
+    }
+
+    /// Return the value, running `init` first if nobody has set it. A core
+    /// that loses the race spins until the winner publishes its value.
+    pub fn get_or_init(&self, init: impl FnOnce() -> T) -> &T {
+        if let Some(value) = self.get() {
+            return value;
+        }
+        match self
+            .state
+            .compare_exchange(EMPTY, BUSY, Ordering::Acquire, Ordering::Acquire)
+        {
+            Ok(_) => {
+                unsafe {
+                    (*self.value.get()).write(init());
+                }
+                self.state.store(READY, Ordering::Release);
+            }
+            Err(_) => {
+                while self.state.load(Ordering::Acquire) != READY {
+                    core::hint::spin_loop();
+                }
+            }
+        }
+        unsafe { (*self.value.get()).assume_init_ref() }
+    }
+}
+
+impl<T> Default for Once<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for Once<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == READY {
+            unsafe { self.value.get_mut().assume_init_drop() }
+        }
+    }
+}
+
+/// Global state owned by the kernel thread.
+///
+/// Unlike `static mut` the type says who may touch it: code running on the
+/// bootstrap core outside interrupt handlers. Interrupt handlers and the other
+/// cores must go through a [`SpinLock`](crate::spinlock::SpinLock) instead.
+pub struct KernelCell<T> {
+    value: UnsafeCell<T>,
+}
+
+// SAFETY: access is confined to the kernel thread by the contract on `get`
+// and `get_mut`, so the value is never actually shared between threads.
+unsafe impl<T> Sync for KernelCell<T> {}
+
+impl<T> KernelCell<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// Kernel thread only, and no reference from [`get_mut`](Self::get_mut)
+    /// may be used while this one is alive.
+    pub unsafe fn get(&self) -> &T {
+        &*self.value.get()
+    }
+
+    /// # Safety
+    ///
+    /// Kernel thread only, and no other reference into the cell may be used
+    /// while this one is alive.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn get_mut(&self) -> &mut T {
+        &mut *self.value.get()
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "sync";
+
+    fn once_keeps_the_first_value() {
+        let cell: Once<u32> = Once::new();
+        crate::selftest::ensure(cell.get().is_none() && !cell.is_set(), "vacio")?;
+        crate::selftest::ensure_eq(cell.set(7), Ok(()), "primer set")?;
+        crate::selftest::ensure_eq(cell.set(9), Err(9), "segundo set")?;
+        crate::selftest::ensure_eq(*cell.get_or_init(|| 11), 7, "get_or_init")?;
+        crate::selftest::ensure_eq(cell.get().copied(), Some(7), "valor")
+    }
+
+    fn once_runs_init_once() {
+        let cell: Once<alloc::string::String> = Once::new();
+        crate::selftest::ensure_eq(cell.get_or_init(|| alloc::string::String::from("a")).as_str(), "a", "init")?;
+        crate::selftest::ensure_eq(cell.get_or_init(|| alloc::string::String::from("b")).as_str(), "a", "reuso")
+    }
+
+    fn kernel_cell_round_trips() {
+        let cell = KernelCell::new(1u64);
+        unsafe {
+            *cell.get_mut() += 41;
+            crate::selftest::ensure_eq(*cell.get(), 42, "valor")
+        }
+    }
+}
@@ -0,0 +1,835 @@
+//! Tiny General MIDI synth with a PC speaker fallback.
+//!
+//! `render` turns notes into mono PCM at `RATE`: every voice runs one of the
+//! built-in patches (one per General MIDI family of eight programs, plus a
+//! drum kit for channel 10) through an ADSR envelope, and the mixer sums the
+//! voices and clips. The result goes out through `audio::play_pcm`. When HDA
+//! did not come up, or `sound.output` is `speaker`, the same notes play as a
+//! single melody line on the PC speaker (PIT channel 2), so alerts are heard
+//! on any machine.
+//!
+//! The named `SOUNDS` back warning and error notifications (and with them
+//! alarms) while `sound.system` is on. `parse_midi` reads Standard MIDI Files
+//! for the `sound midi` command, and ReduxLang scripts call `beep`,
+//! `play_note` and `play_sound` through `run_script_op`.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::config::ConfigValue;
+use crate::hal::{inb, outb};
+use crate::spinlock::SpinLock;
+use crate::timer::TimerId;
+
+pub const RATE: u32 = 48_000;
+/// The HDA DMA buffer holds eight seconds of mono 16-bit audio.
+pub const MAX_RENDER_MS: u32 = 8_000;
+const MAX_NOTES: usize = 4096;
+const PIT_HZ: u32 = 1_193_182;
+pub const SYSTEM_KEY: &str = "sound.system";
+pub const OUTPUT_KEY: &str = "sound.output";
+/// Channel 10 in MIDI numbering.
+const DRUM_CHANNEL: u8 = 9;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Note {
+    pub start_ms: u32,
+    pub duration_ms: u32,
+    pub key: u8,
+    pub velocity: u8,
+    /// General MIDI program, 0-127.
+    pub program: u8,
+    /// Played by the drum kit; `key` picks the instrument.
+    pub drum: bool,
+}
+
+impl Note {
+    pub const fn tone(start_ms: u32, duration_ms: u32, key: u8, program: u8) -> Self {
+        Self {
+            start_ms,
+            duration_ms,
+            key,
+            velocity: 100,
+            program,
+            drum: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Wave {
+    Sine,
+    Triangle,
+    Square,
+    Saw,
+    Noise,
+}
+
+#[derive(Clone, Copy)]
+struct Patch {
+    name: &'static str,
+    wave: Wave,
+    attack_ms: u32,
+    decay_ms: u32,
+    /// Level held after the decay, 0-255.
+    sustain: u32,
+    release_ms: u32,
+}
+
+const fn patch(name: &'static str, wave: Wave, attack_ms: u32, decay_ms: u32, sustain: u32, release_ms: u32) -> Patch {
+    Patch {
+        name,
+        wave,
+        attack_ms,
+        decay_ms,
+        sustain,
+        release_ms,
+    }
+}
+
+/// One patch per General MIDI family (program / 8).
+const PATCHES: [Patch; 16] = [
+    patch("piano", Wave::Triangle, 2, 600, 40, 120),
+    patch("percusion cromatica", Wave::Sine, 1, 400, 0, 200),
+    patch("organo", Wave::Sine, 5, 0, 255, 60),
+    patch("guitarra", Wave::Saw, 2, 500, 50, 150),
+    patch("bajo", Wave::Triangle, 5, 300, 120, 80),
+    patch("cuerdas", Wave::Saw, 80, 0, 255, 200),
+    patch("conjunto", Wave::Saw, 120, 0, 230, 300),
+    patch("metales", Wave::Square, 30, 200, 180, 100),
+    patch("lengueta", Wave::Square, 20, 100, 200, 80),
+    patch("flauta", Wave::Sine, 40, 0, 230, 120),
+    patch("sintetizador solista", Wave::Square, 5, 0, 255, 60),
+    patch("sintetizador fondo", Wave::Triangle, 300, 0, 255, 400),
+    patch("efectos de sintetizador", Wave::Saw, 100, 800, 100, 400),
+    patch("etnico", Wave::Triangle, 2, 400, 60, 150),
+    patch("percusion", Wave::Sine, 1, 200, 0, 100),
+    patch("efectos", Wave::Noise, 10, 300, 80, 200),
+];
+
+/// Drum kit voice for a channel 10 key: wave, fixed pitch (0 for noise)
+/// and decay.
+fn drum_voice(key: u8) -> (Wave, u8, u32) {
+    match key {
+        35 | 36 => (Wave::Sine, 28, 160),
+        38 | 40 => (Wave::Noise, 0, 180),
+        42 | 44 => (Wave::Noise, 0, 50),
+        46 => (Wave::Noise, 0, 300),
+        49 | 57 => (Wave::Noise, 0, 900),
+        41 | 43 | 45 | 47 | 48 | 50 => (Wave::Sine, key, 220),
+        _ => (Wave::Noise, 0, 120),
+    }
+}
+
+/// Frequencies of MIDI keys 0-11 (octave -1), in millihertz.
+const OCTAVE_MILLIHZ: [u32; 12] = [
+    8176, 8662, 9177, 9723, 10301, 10913, 11562, 12250, 12978, 13750, 14568, 15434,
+];
+
+pub fn key_millihz(key: u8) -> u32 {
+    let key = key.min(127);
+    OCTAVE_MILLIHZ[(key % 12) as usize] << (key / 12)
+}
+
+/// Envelope level 0-255 at `t` ms into a note held for `held` ms.
+fn envelope(p: &Patch, t: u32, held: u32) -> u32 {
+    let level_at = |t: u32| {
+        if t < p.attack_ms {
+            t * 255 / p.attack_ms
+        } else if t < p.attack_ms + p.decay_ms {
+            255 - (255 - p.sustain) * (t - p.attack_ms) / p.decay_ms
+        } else {
+            p.sustain
+        }
+    };
+    if t < held {
+        return level_at(t);
+    }
+    let from = level_at(held);
+    let since = t - held;
+    if since >= p.release_ms {
+        0
+    } else {
+        from * (p.release_ms - since) / p.release_ms
+    }
+}
+
+/// One cycle of `wave` at `phase` (a full turn is 2^32), full scale.
+fn oscillate(wave: Wave, phase: u32, noise: &mut u32) -> i32 {
+    let saw = (phase >> 16) as i32 - 32768;
+    match wave {
+        // Parabolic approximation of sin(pi * saw / 32768).
+        Wave::Sine => ((4 * saw as i64 * (32768 - saw.abs()) as i64) >> 15).clamp(-32767, 32767) as i32,
+        Wave::Triangle => 2 * saw.abs() - 32768,
+        Wave::Square => {
+            if phase < 1 << 31 {
+                20000
+            } else {
+                -20000
+            }
+        }
+        Wave::Saw => saw,
+        Wave::Noise => {
+            *noise ^= *noise << 13;
+            *noise ^= *noise >> 17;
+            *noise ^= *noise << 5;
+            (*noise >> 16) as i32 - 32768
+        }
+    }
+}
+
+fn voice(note: &Note) -> (Patch, u32) {
+    if note.drum {
+        let (wave, pitch, decay_ms) = drum_voice(note.key);
+        (patch("bateria", wave, 1, decay_ms, 0, 20), key_millihz(pitch))
+    } else {
+        (
+            PATCHES[(note.program / 8) as usize % PATCHES.len()],
+            key_millihz(note.key),
+        )
+    }
+}
+
+/// How long `notes` sound, release tails included.
+pub fn length_ms(notes: &[Note]) -> u32 {
+    notes
+        .iter()
+        .map(|note| note.start_ms + note.duration_ms + voice(note).0.release_ms)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Mix `notes` into mono samples at `RATE`, cut at `max_ms`.
+pub fn render(notes: &[Note], max_ms: u32) -> Vec<i16> {
+    let total_ms = length_ms(notes).min(max_ms);
+    let len = (total_ms as u64 * RATE as u64 / 1000) as usize;
+    let mut mix = alloc::vec![0i32; len];
+    for (index, note) in notes.iter().enumerate() {
+        let (p, millihz) = voice(note);
+        let step = (millihz as u64 * (1u64 << 32) / (RATE as u64 * 1000)) as u32;
+        let first = (note.start_ms as u64 * RATE as u64 / 1000) as usize;
+        let last = ((note.start_ms + note.duration_ms + p.release_ms) as u64 * RATE as u64 / 1000) as usize;
+        let mut phase = 0u32;
+        let mut noise = 0x9E37_79B9u32 ^ index as u32;
+        for (i, slot) in mix.iter_mut().enumerate().take(last.min(len)).skip(first) {
+            let t = ((i - first) as u64 * 1000 / RATE as u64) as u32;
+            let level = envelope(&p, t, note.duration_ms);
+            if level != 0 {
+                let sample = oscillate(p.wave, phase, &mut noise);
+                // A quarter of full scale per voice leaves room for chords.
+                *slot += sample * level as i32 / 255 * note.velocity.min(127) as i32 / 127 / 4;
+            }
+            phase = phase.wrapping_add(step);
+        }
+    }
+    mix.into_iter()
+        .map(|s| s.clamp(i16::MIN as i32, i16::MAX as i32) as i16)
+        .collect()
+}
+
+/// The melody the PC speaker can play: (hertz, ms) steps, 0 Hz for rests.
+/// Overlapping notes give way to the one that starts next; drums are left
+/// out.
+pub fn speaker_line(notes: &[Note]) -> Vec<(u32, u32)> {
+    let mut tones: Vec<&Note> = notes.iter().filter(|note| !note.drum).collect();
+    tones.sort_by_key(|note| note.start_ms);
+    let mut line = Vec::new();
+    let mut at = 0u32;
+    for (i, note) in tones.iter().enumerate() {
+        if note.start_ms < at {
+            continue;
+        }
+        if note.start_ms > at {
+            line.push((0, note.start_ms - at));
+        }
+        let next = tones[i + 1..]
+            .iter()
+            .map(|n| n.start_ms)
+            .find(|start| *start > note.start_ms);
+        let end = next.map_or(note.start_ms + note.duration_ms, |next| {
+            next.min(note.start_ms + note.duration_ms)
+        });
+        line.push((key_millihz(note.key) / 1000, end - note.start_ms));
+        at = end;
+    }
+    line
+}
+
+// ─── Standard MIDI Files ─────────────────────────────────────────────────────
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn byte(&mut self) -> Result<u8, &'static str> {
+        let byte = *self.bytes.get(self.pos).ok_or("MIDI truncado")?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], &'static str> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or("MIDI truncado")?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u32_be(&mut self) -> Result<u32, &'static str> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u16_be(&mut self) -> Result<u16, &'static str> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    /// Variable length quantity, at most four bytes.
+    fn varlen(&mut self) -> Result<u32, &'static str> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = self.byte()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("longitud MIDI no valida")
+    }
+}
+
+#[derive(Clone, Copy)]
+enum MidiEvent {
+    NoteOn { channel: u8, key: u8, velocity: u8 },
+    NoteOff { channel: u8, key: u8 },
+    Program { channel: u8, program: u8 },
+    Tempo(u32),
+}
+
+fn parse_track(track: &[u8], events: &mut Vec<(u64, MidiEvent)>) -> Result<(), &'static str> {
+    let mut cursor = Cursor { bytes: track, pos: 0 };
+    let mut tick = 0u64;
+    let mut running = 0u8;
+    while cursor.pos < track.len() {
+        tick += cursor.varlen()? as u64;
+        let mut status = cursor.byte()?;
+        let first = if status < 0x80 {
+            // Running status: this byte is already the first data byte.
+            let data = status;
+            status = running;
+            data
+        } else {
+            if status < 0xF0 {
+                running = status;
+            }
+            match status {
+                0xFF => {
+                    let kind = cursor.byte()?;
+                    let len = cursor.varlen()? as usize;
+                    let data = cursor.take(len)?;
+                    match kind {
+                        0x2F => return Ok(()),
+                        0x51 if len == 3 => {
+                            let tempo = u32::from_be_bytes([0, data[0], data[1], data[2]]);
+                            events.push((tick, MidiEvent::Tempo(tempo)));
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                0xF0 | 0xF7 => {
+                    let len = cursor.varlen()? as usize;
+                    cursor.take(len)?;
+                    continue;
+                }
+                _ => cursor.byte()?,
+            }
+        };
+        let channel = status & 0x0F;
+        match status & 0xF0 {
+            0x80 => {
+                cursor.byte()?;
+                events.push((tick, MidiEvent::NoteOff { channel, key: first }));
+            }
+            0x90 => {
+                let velocity = cursor.byte()?;
+                events.push((
+                    tick,
+                    if velocity == 0 {
+                        MidiEvent::NoteOff { channel, key: first }
+                    } else {
+                        MidiEvent::NoteOn {
+                            channel,
+                            key: first,
+                            velocity,
+                        }
+                    },
+                ));
+            }
+            0xA0 | 0xB0 | 0xE0 => {
+                cursor.byte()?;
+            }
+            0xC0 => events.push((
+                tick,
+                MidiEvent::Program {
+                    channel,
+                    program: first,
+                },
+            )),
+            0xD0 => {}
+            _ => return Err("evento MIDI no valido"),
+        }
+    }
+    Ok(())
+}
+
+/// Notes of a Standard MIDI File (format 0 or 1) with their times in ms.
+pub fn parse_midi(bytes: &[u8]) -> Result<Vec<Note>, &'static str> {
+    let mut cursor = Cursor { bytes, pos: 0 };
+    if cursor.take(4)? != b"MThd" {
+        return Err("no es un archivo MIDI");
+    }
+    let header_len = cursor.u32_be()? as usize;
+    if header_len < 6 {
+        return Err("cabecera MIDI corta");
+    }
+    let format = cursor.u16_be()?;
+    let _tracks = cursor.u16_be()?;
+    let division = cursor.u16_be()?;
+    cursor.take(header_len - 6)?;
+    if format > 1 {
+        return Err("formato MIDI 2 no soportado");
+    }
+    if division & 0x8000 != 0 || division == 0 {
+        return Err("division SMPTE no soportada");
+    }
+
+    let mut events: Vec<(u64, MidiEvent)> = Vec::new();
+    while cursor.pos + 8 <= bytes.len() {
+        let id = cursor.take(4)?;
+        let len = cursor.u32_be()? as usize;
+        let chunk = cursor.take(len)?;
+        if id == b"MTrk" {
+            parse_track(chunk, &mut events)?;
+        }
+    }
+    // Stable: events at the same tick keep their track order.
+    events.sort_by_key(|(tick, _)| *tick);
+
+    let mut notes = Vec::new();
+    let mut programs = [0u8; 16];
+    let mut held: Vec<(u8, u8, u8, u32)> = Vec::new();
+    let (mut last_tick, mut last_us, mut tempo) = (0u64, 0u64, 500_000u64);
+    for (tick, event) in events {
+        last_us += (tick - last_tick) * tempo / division as u64;
+        last_tick = tick;
+        let now_ms = (last_us / 1000).min(u32::MAX as u64) as u32;
+        match event {
+            MidiEvent::Tempo(us) => tempo = us.max(1) as u64,
+            MidiEvent::Program { channel, program } => programs[channel as usize] = program,
+            MidiEvent::NoteOn { channel, key, velocity } => held.push((channel, key, velocity, now_ms)),
+            MidiEvent::NoteOff { channel, key } => {
+                if let Some(index) = held.iter().position(|h| h.0 == channel && h.1 == key) {
+                    let (channel, key, velocity, start_ms) = held.remove(index);
+                    notes.push(Note {
+                        start_ms,
+                        duration_ms: now_ms - start_ms,
+                        key,
+                        velocity,
+                        program: programs[channel as usize],
+                        drum: channel == DRUM_CHANNEL,
+                    });
+                    if notes.len() >= MAX_NOTES {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    notes.sort_by_key(|note| note.start_ms);
+    Ok(notes)
+}
+
+// ─── Output ──────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Output {
+    Hda,
+    Speaker,
+}
+
+impl Output {
+    pub fn label(self) -> &'static str {
+        match self {
+            Output::Hda => "HDA",
+            Output::Speaker => "altavoz del PC",
+        }
+    }
+}
+
+pub fn output() -> Output {
+    let forced = crate::config::get_str(OUTPUT_KEY, "auto");
+    if forced.as_str() != "speaker" && crate::audio::is_ready() {
+        Output::Hda
+    } else {
+        Output::Speaker
+    }
+}
+
+struct Speaker {
+    queue: VecDeque<(u32, u32)>,
+    timer: Option<TimerId>,
+}
+
+static SPEAKER: SpinLock<Speaker> = SpinLock::new(Speaker {
+    queue: VecDeque::new(),
+    timer: None,
+});
+
+/// Square wave from PIT channel 2 on the speaker; 0 Hz silences it.
+fn speaker_tone(hz: u32) {
+    unsafe {
+        let gate = inb(0x61);
+        if hz == 0 {
+            outb(0x61, gate & !0x03);
+            return;
+        }
+        let divisor = (PIT_HZ / hz).clamp(1, 0xFFFF);
+        outb(0x43, 0xB6);
+        outb(0x42, (divisor & 0xFF) as u8);
+        outb(0x42, (divisor >> 8) as u8);
+        outb(0x61, gate | 0x03);
+    }
+}
+
+fn on_speaker_step(_arg: usize) {
+    let mut speaker = SPEAKER.lock();
+    speaker.timer = None;
+    match speaker.queue.pop_front() {
+        Some((hz, ms)) => {
+            speaker_tone(hz);
+            speaker.timer = Some(crate::timer::after_ms(ms.max(1) as u64, on_speaker_step, 0));
+        }
+        None => speaker_tone(0),
+    }
+}
+
+/// Replace whatever the speaker is playing with `line`.
+pub fn speaker_play(line: Vec<(u32, u32)>) {
+    {
+        let mut speaker = SPEAKER.lock();
+        if let Some(timer) = speaker.timer.take() {
+            crate::timer::cancel(timer);
+        }
+        speaker.queue = line.into();
+    }
+    on_speaker_step(0);
+}
+
+/// Play `notes` on the best output available; returns the one used.
+pub fn play(notes: &[Note]) -> Output {
+    let out = output();
+    match out {
+        Output::Hda => {
+            let pcm = render(notes, MAX_RENDER_MS);
+            let bytes: Vec<u8> = pcm.iter().flat_map(|s| s.to_le_bytes()).collect();
+            crate::audio::play_pcm(bytes.as_slice(), RATE, 1);
+        }
+        Output::Speaker => speaker_play(speaker_line(notes)),
+    }
+    out
+}
+
+// ─── System sounds ───────────────────────────────────────────────────────────
+
+/// Name, General MIDI program and (key, ms) steps; key 0 is a rest.
+type Sound = (&'static str, u8, &'static [(u8, u32)]);
+
+pub const SOUNDS: &[Sound] = &[
+    ("notify", 9, &[(76, 90), (83, 160)]),
+    ("success", 9, &[(72, 90), (76, 90), (79, 180)]),
+    ("warning", 80, &[(81, 150), (0, 60), (81, 150), (0, 60), (81, 150)]),
+    ("error", 80, &[(57, 140), (52, 260)]),
+    ("startup", 0, &[(60, 140), (64, 140), (67, 140), (72, 400)]),
+];
+
+pub fn sound_notes(name: &str) -> Option<Vec<Note>> {
+    let (_, program, steps) = SOUNDS.iter().find(|(sound, _, _)| sound.eq_ignore_ascii_case(name))?;
+    let mut at = 0;
+    let mut notes = Vec::new();
+    for (key, ms) in steps.iter() {
+        if *key != 0 {
+            notes.push(Note::tone(at, *ms, *key, *program));
+        }
+        at += ms;
+    }
+    Some(notes)
+}
+
+pub fn play_sound(name: &str) -> Result<Output, &'static str> {
+    let notes = sound_notes(name).ok_or("sonido desconocido")?;
+    Ok(play(notes.as_slice()))
+}
+
+pub fn system_sounds_enabled() -> bool {
+    crate::config::get_bool(SYSTEM_KEY, true)
+}
+
+/// Alert for a notification: warnings (alarms among them) and errors only.
+/// Leaves media playback alone.
+pub fn notification_sound(urgency: crate::gui::notifications::Urgency) {
+    use crate::gui::notifications::Urgency;
+    let name = match urgency {
+        Urgency::Warning => "warning",
+        Urgency::Error => "error",
+        _ => return,
+    };
+    if !system_sounds_enabled() || crate::audio::is_playing() {
+        return;
+    }
+    let _ = play_sound(name);
+}
+
+/// Play one ReduxLang sound op ("beep <hz> <ms>", "note <key> <ms> <program>"
+/// or "sound <name>"); returns how long it lasts so the script can wait.
+pub fn run_script_op(op: &str) -> u32 {
+    let mut parts = op.split_whitespace();
+    let kind = parts.next().unwrap_or("");
+    let mut number = |default: u32| parts.next().and_then(|p| p.parse::<u32>().ok()).unwrap_or(default);
+    match kind {
+        "beep" => {
+            let hz = number(880).clamp(20, 20_000);
+            let ms = number(200).min(MAX_RENDER_MS);
+            speaker_or_tone(hz, ms);
+            ms
+        }
+        "note" => {
+            let key = number(60).min(127) as u8;
+            let ms = number(300).min(MAX_RENDER_MS);
+            let program = number(0).min(127) as u8;
+            play(&[Note::tone(0, ms, key, program)]);
+            ms
+        }
+        "sound" => {
+            let name = parts.next().unwrap_or("");
+            match sound_notes(name) {
+                Some(notes) => {
+                    play(notes.as_slice());
+                    length_ms(notes.as_slice())
+                }
+                None => 0,
+            }
+        }
+        _ => 0,
+    }
+}
+
+/// A bare tone: exact pitch on the speaker, nearest key on HDA.
+fn speaker_or_tone(hz: u32, ms: u32) {
+    match output() {
+        Output::Speaker => speaker_play(alloc::vec![(hz, ms)]),
+        Output::Hda => {
+            let key = (0..=127u8)
+                .min_by_key(|key| (key_millihz(*key) / 1000).abs_diff(hz))
+                .unwrap_or(69);
+            play(&[Note::tone(0, ms, key, 80)]);
+        }
+    }
+}
+
+fn parse_number(text: Option<&str>, default: u32) -> Option<u32> {
+    match text {
+        None => Some(default),
+        Some(text) => text.parse::<u32>().ok(),
+    }
+}
+
+/// Shared implementation of `sound [status|list|play <nombre>|beep [hz] [ms]|
+/// note <n> [ms] [programa]|midi <archivo>|on|off|output auto|speaker]`.
+pub fn command_lines(args: &str, dir_cluster: u32) -> Vec<String> {
+    let args: Vec<&str> = args.split_whitespace().collect();
+    let mut out = Vec::new();
+    match args.as_slice() {
+        [] | ["status"] => {
+            out.push(alloc::format!(
+                "Salida: {} (HDA: {})",
+                output().label(),
+                crate::audio::status_text()
+            ));
+            out.push(alloc::format!(
+                "Sonidos del sistema: {}",
+                if system_sounds_enabled() { "activados" } else { "desactivados" }
+            ));
+        }
+        ["list"] => {
+            out.push(String::from("Sonidos:"));
+            for (name, program, _) in SOUNDS.iter() {
+                out.push(alloc::format!("  {} ({})", name, PATCHES[(*program / 8) as usize].name));
+            }
+            out.push(String::from("Instrumentos (programa GM):"));
+            for (family, p) in PATCHES.iter().enumerate() {
+                out.push(alloc::format!("  {:3}-{:3} {}", family * 8, family * 8 + 7, p.name));
+            }
+        }
+        ["play", name] => match play_sound(name) {
+            Ok(used) => out.push(alloc::format!("Sonido '{}' por {}.", name, used.label())),
+            Err(e) => out.push(alloc::format!("sound: {}", e)),
+        },
+        ["beep", rest @ ..] if rest.len() <= 2 => {
+            match (parse_number(rest.first().copied(), 880), parse_number(rest.get(1).copied(), 200)) {
+                (Some(hz), Some(ms)) => {
+                    let hz = hz.clamp(20, 20_000);
+                    let ms = ms.min(MAX_RENDER_MS);
+                    speaker_or_tone(hz, ms);
+                    out.push(alloc::format!("Pitido de {} Hz, {} ms.", hz, ms));
+                }
+                _ => out.push(String::from("Uso: sound beep [hz] [ms]")),
+            }
+        }
+        ["note", key, rest @ ..] if rest.len() <= 2 => {
+            match (
+                key.parse::<u8>().ok().filter(|key| *key <= 127),
+                parse_number(rest.first().copied(), 300),
+                parse_number(rest.get(1).copied(), 0).filter(|p| *p <= 127),
+            ) {
+                (Some(key), Some(ms), Some(program)) => {
+                    let note = Note::tone(0, ms.min(MAX_RENDER_MS), key, program as u8);
+                    let used = play(&[note]);
+                    out.push(alloc::format!(
+                        "Nota {} ({} Hz) con {} por {}.",
+                        key,
+                        key_millihz(key) / 1000,
+                        PATCHES[(program / 8) as usize].name,
+                        used.label()
+                    ));
+                }
+                _ => out.push(String::from("Uso: sound note <0-127> [ms] [programa 0-127]")),
+            }
+        }
+        ["midi", name] => {
+            let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+            let notes = fat
+                .read_file_in_dir(dir_cluster, name)
+                .and_then(|raw| parse_midi(raw.as_slice()));
+            match notes {
+                Ok(notes) if notes.is_empty() => out.push(String::from("sound: el archivo no tiene notas")),
+                Ok(notes) => {
+                    let total = length_ms(notes.as_slice());
+                    let used = play(notes.as_slice());
+                    out.push(alloc::format!(
+                        "{}: {} notas, {} por {}.",
+                        name,
+                        notes.len(),
+                        crate::wav::format_time(total),
+                        used.label()
+                    ));
+                    if used == Output::Hda && total > MAX_RENDER_MS {
+                        out.push(alloc::format!(
+                            "Solo suenan los primeros {} s.",
+                            MAX_RENDER_MS / 1000
+                        ));
+                    }
+                }
+                Err(e) => out.push(alloc::format!("sound: {}: {}", name, e)),
+            }
+        }
+        ["on"] | ["off"] => {
+            let on = args[0] == "on";
+            match crate::config::set(SYSTEM_KEY, ConfigValue::Bool(on)) {
+                Ok(()) => out.push(alloc::format!(
+                    "Sonidos del sistema {}.",
+                    if on { "activados" } else { "desactivados" }
+                )),
+                Err(e) => out.push(alloc::format!("config: {}", e)),
+            }
+        }
+        ["output", mode @ ("auto" | "speaker")] => {
+            match crate::config::set(OUTPUT_KEY, ConfigValue::Str(String::from(*mode))) {
+                Ok(()) => out.push(alloc::format!("Salida: {}", output().label())),
+                Err(e) => out.push(alloc::format!("config: {}", e)),
+            }
+        }
+        _ => out.push(String::from(
+            "Uso: sound [status|list|play <nombre>|beep [hz] [ms]|note <n> [ms] [programa]|midi <archivo>|on|off|output auto|speaker]",
+        )),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "synth";
+
+    fn keys_map_to_equal_temperament() {
+        crate::selftest::ensure_eq(key_millihz(69), 440_000, "la 440")?;
+        crate::selftest::ensure_eq(key_millihz(60), 261_632, "do central")?;
+        crate::selftest::ensure_eq(key_millihz(81), 880_000, "octava")
+    }
+
+    fn midi_files_become_timed_notes() {
+        // Format 0, 96 ticks per quarter, 120 bpm: program 80, C4 for a
+        // quarter, then E4 for an eighth with running status and a
+        // velocity 0 note off.
+        let mut midi = Vec::new();
+        midi.extend_from_slice(b"MThd\0\0\0\x06\0\0\0\x01\0\x60");
+        let track: &[u8] = &[
+            0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // tempo 500000
+            0x00, 0xC0, 80, // program change
+            0x00, 0x90, 60, 100, // C4 on
+            0x60, 0x80, 60, 0, // C4 off after 96 ticks
+            0x00, 0x90, 64, 90, // E4 on
+            0x30, 64, 0, // running status, velocity 0
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        midi.extend_from_slice(b"MTrk");
+        midi.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        midi.extend_from_slice(track);
+        let notes = parse_midi(midi.as_slice())?;
+        let mut second = Note::tone(500, 250, 64, 80);
+        second.velocity = 90;
+        crate::selftest::ensure_eq(notes.as_slice(), &[Note::tone(0, 500, 60, 80), second][..], "notas")?;
+        crate::selftest::ensure_eq(parse_midi(b"RIFF"), Err("no es un archivo MIDI"), "cabecera")
+    }
+
+    fn voices_are_mixed_and_clipped() {
+        let organ = 16;
+        let chord: Vec<Note> = [48, 55, 60, 64, 67, 72, 76, 79]
+            .iter()
+            .map(|key| Note::tone(0, 100, *key, organ))
+            .collect();
+        let pcm = render(chord.as_slice(), MAX_RENDER_MS);
+        crate::selftest::ensure_eq(pcm.len(), (length_ms(chord.as_slice()) * RATE / 1000) as usize, "largo")?;
+        crate::selftest::ensure(pcm.iter().any(|s| s.unsigned_abs() > 8000), "suena")?;
+        let tail = &pcm[pcm.len() - (RATE / 1000) as usize..];
+        crate::selftest::ensure(tail.iter().all(|s| s.unsigned_abs() < 2000), "se apaga")?;
+        let rest = render(&[Note::tone(200, 50, 60, organ)], MAX_RENDER_MS);
+        crate::selftest::ensure(rest[..200 * RATE as usize / 1000].iter().all(|s| *s == 0), "silencio inicial")?;
+        crate::selftest::ensure_eq(render(chord.as_slice(), 50).len(), (50 * RATE / 1000) as usize, "recorte")
+    }
+
+    fn speaker_plays_the_top_line_with_rests() {
+        let mut drum = Note::tone(0, 100, 36, 0);
+        drum.drum = true;
+        let notes = [
+            Note::tone(0, 300, 69, 0),
+            Note::tone(200, 100, 81, 0),
+            Note::tone(200, 100, 76, 0),
+            Note::tone(500, 100, 60, 0),
+            drum,
+        ];
+        crate::selftest::ensure_eq(
+            speaker_line(&notes),
+            alloc::vec![(440, 200), (880, 100), (0, 200), (261, 100)],
+            "linea",
+        )?;
+        let warning = sound_notes("warning").ok_or_else(|| String::from("sin sonido"))?;
+        crate::selftest::ensure_eq(warning.len(), 3, "tres pitidos")?;
+        crate::selftest::ensure_eq(warning[2].start_ms, 420, "con silencios")
+    }
+}
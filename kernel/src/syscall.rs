@@ -17,12 +17,70 @@ pub const SYS_SYSCALL_COUNT: usize = 6;
 pub const SYS_PRIV_STATUS: usize = 7;
 pub const SYS_PRIV_NEXT_PHASE: usize = 8;
 pub const SYS_PRIV_UNSAFE_TEST: usize = 9;
-
-pub const SYS_COUNT: usize = 10;
+pub const SYS_HTTP_OPEN: usize = 10;
+pub const SYS_HTTP_SET_HEADER: usize = 11;
+pub const SYS_HTTP_SEND: usize = 12;
+pub const SYS_HTTP_HEADERS: usize = 13;
+pub const SYS_HTTP_READ: usize = 14;
+pub const SYS_HTTP_CLOSE: usize = 15;
+pub const SYS_CONFIG_GET: usize = 16;
+pub const SYS_CONFIG_SET: usize = 17;
+pub const SYS_CONFIG_WATCH: usize = 18;
+pub const SYS_HTTP_SET_OPTION: usize = 19;
+pub const SYS_GET_RANDOM: usize = 20;
+pub const SYS_FS_WATCH: usize = 21;
+pub const SYS_FS_READ_EVENTS: usize = 22;
+pub const SYS_FS_UNWATCH: usize = 23;
+pub const SYS_AUDIO_CAPTURE_OPEN: usize = 24;
+pub const SYS_AUDIO_CAPTURE_READ: usize = 25;
+pub const SYS_AUDIO_CAPTURE_CLOSE: usize = 26;
+
+pub const SYS_COUNT: usize = 27;
 
 pub const SYS_ERR_BAD_SYSCALL: u64 = u64::MAX - 1;
 pub const SYS_ERR_BAD_THREAD: u64 = u64::MAX - 2;
 pub const SYS_ERR_PERMISSION: u64 = u64::MAX - 3;
+pub const SYS_ERR_NET: u64 = u64::MAX - 4;
+pub const SYS_ERR_QUOTA: u64 = u64::MAX - 5;
+pub const SYS_ERR_DENIED: u64 = u64::MAX - 6;
+pub const SYS_ERR_BAD_HANDLE: u64 = u64::MAX - 7;
+pub const SYS_ERR_UNSUPPORTED: u64 = u64::MAX - 8;
+pub const SYS_ERR_INVALID: u64 = u64::MAX - 9;
+pub const SYS_ERR_NOT_FOUND: u64 = u64::MAX - 10;
+
+/// Linux ELF processes reach the native calls below at `REDUX_CALL_BASE + id`,
+/// far above the Linux numbers; this is the ABI `sdk/redux-sdk` binds. The
+/// shell and privilege calls are left out.
+pub const REDUX_CALL_BASE: u64 = 0x5200;
+const REDUX_CALLS: [usize; 15] = [
+    SYS_GET_TICK,
+    SYS_HTTP_OPEN,
+    SYS_HTTP_SET_HEADER,
+    SYS_HTTP_SEND,
+    SYS_HTTP_HEADERS,
+    SYS_HTTP_READ,
+    SYS_HTTP_CLOSE,
+    SYS_CONFIG_GET,
+    SYS_CONFIG_SET,
+    SYS_CONFIG_WATCH,
+    SYS_HTTP_SET_OPTION,
+    SYS_GET_RANDOM,
+    SYS_FS_WATCH,
+    SYS_FS_READ_EVENTS,
+    SYS_FS_UNWATCH,
+];
+/// Thread index the native handlers see for a call from a Linux ELF.
+const LINUX_ELF_THREAD: usize = usize::MAX;
+
+const SYS_HTTP_MAX_ARG: usize = 4096;
+/// Registry keys ring 3 may read but not change.
+const SYS_CONFIG_PROTECTED_PREFIX: &str = "system.";
+/// Most bytes one SYS_GET_RANDOM call fills.
+const SYS_GET_RANDOM_MAX: usize = 4096;
+/// Most bytes of event records one SYS_FS_READ_EVENTS call returns.
+const SYS_FS_EVENTS_MAX: usize = 4096;
+
+const SYS_AUDIO_CAPTURE_READ_MAX: usize = 16384;
 
 const CMD_QUEUE_CAP: usize = 16;
 const LINUX_MAX_MMAPS: usize = 64;
@@ -179,6 +237,7 @@ const LINUX_SYS_SIGALTSTACK: u64 = 131;
 const LINUX_SYS_GETEUID: u64 = 107;
 const LINUX_SYS_GETEGID: u64 = 108;
 const LINUX_SYS_GETCWD: u64 = 79;
+const LINUX_SYS_CHDIR: u64 = 80;
 const LINUX_SYS_READLINK: u64 = 89;
 const LINUX_SYS_GETTIMEOFDAY: u64 = 96;
 const LINUX_SYS_GETRLIMIT: u64 = 97;
@@ -241,6 +300,9 @@ const LINUX_SYS_OPENAT2: u64 = 437;
 const LINUX_SYS_FACCESSAT2: u64 = 439;
 const LINUX_SYS_FUTEX_WAITV: u64 = 449;
 
+const LINUX_PROT_READ: u64 = 0x1;
+const LINUX_PROT_WRITE: u64 = 0x2;
+const LINUX_PROT_EXEC: u64 = 0x4;
 const LINUX_MAP_SHARED: u64 = 0x01;
 const LINUX_MAP_PRIVATE: u64 = 0x02;
 const LINUX_MAP_FIXED: u64 = 0x10;
@@ -603,6 +665,8 @@ struct LinuxMmapSlot {
     flags: u64,
     backing_ptr: u64,
     backing_len: u64,
+    /// Some backing page was made executable (see `linux_apply_mmap_prot`).
+    exec_pages: bool,
 }
 
 impl LinuxMmapSlot {
@@ -616,6 +680,7 @@ impl LinuxMmapSlot {
             flags: 0,
             backing_ptr: 0,
             backing_len: 0,
+            exec_pages: false,
         }
     }
 }
@@ -630,6 +695,8 @@ struct LinuxProcessSlot {
     brk_base: u64,
     brk_current: u64,
     brk_limit: u64,
+    /// Start of this process's mmap area (randomized per exec).
+    mmap_base: u64,
     mmap_cursor: u64,
     mmap_count: usize,
 }
@@ -645,6 +712,7 @@ impl LinuxProcessSlot {
             brk_base: 0,
             brk_current: 0,
             brk_limit: 0,
+            mmap_base: LINUX_MMAP_BASE,
             mmap_cursor: LINUX_MMAP_BASE,
             mmap_count: 0,
         }
@@ -701,12 +769,17 @@ const LINUX_OPEN_KIND_SOCKET: u8 = 7;
 const LINUX_OPEN_KIND_PIDFD: u8 = 8;
 const LINUX_OPEN_KIND_DIR: u8 = 9;
 const LINUX_OPEN_KIND_FAT32: u8 = 10;
+const LINUX_OPEN_KIND_VFS: u8 = 11;
+/// Largest single read/write forwarded to a `crate::fs::vfs` handle.
+const LINUX_VFS_IO_CHUNK: u64 = 1024 * 1024;
 const LINUX_OPEN_AUX_TIMERFD: u64 = 0x5446_4D52; // "TFMR"
 
 const LINUX_O_WRONLY: u64 = 0x0000_0001;
 const LINUX_O_RDWR: u64 = 0x0000_0002;
 const LINUX_O_CREAT: u64 = 0x0000_0040;
 const LINUX_O_EXCL: u64 = 0x0000_0080;
+const LINUX_O_TRUNC: u64 = 0x0000_0200;
+const LINUX_O_APPEND: u64 = 0x0000_0400;
 const LINUX_O_DIRECTORY: u64 = 0x0001_0000;
 const LINUX_O_CLOEXEC: u64 = 0x0008_0000;
 const LINUX_AT_EMPTY_PATH: u64 = 0x1000;
@@ -830,6 +903,8 @@ const LINUX_X11_ATOM_NET_NUMBER_OF_DESKTOPS: u32 = 0x0100_001B;
 const LINUX_X11_ATOM_NET_DESKTOP_NAMES: u32 = 0x0100_001C;
 const LINUX_X11_ATOM_NET_CLIENT_LIST: u32 = 0x0100_001D;
 const LINUX_X11_ATOM_MOTIF_WM_HINTS: u32 = 0x0100_001E;
+/// Root property the server asks CLIPBOARD owners to convert into.
+const LINUX_X11_ATOM_REDUX_CLIPBOARD: u32 = 0x0100_001F;
 const LINUX_X11_EVENT_CLIENT_MESSAGE: u8 = 33;
 const LINUX_X11_EVENT_KEY_PRESS: u8 = 2;
 const LINUX_X11_EVENT_KEY_RELEASE: u8 = 3;
@@ -842,6 +917,8 @@ const LINUX_X11_EVENT_UNMAP_NOTIFY: u8 = 18;
 const LINUX_X11_EVENT_MAP_NOTIFY: u8 = 19;
 const LINUX_X11_EVENT_CONFIGURE_NOTIFY: u8 = 22;
 const LINUX_X11_EVENT_PROPERTY_NOTIFY: u8 = 28;
+const LINUX_X11_EVENT_SELECTION_CLEAR: u8 = 29;
+const LINUX_X11_EVENT_SELECTION_REQUEST: u8 = 30;
 const LINUX_X11_EVENT_SELECTION_NOTIFY: u8 = 31;
 const LINUX_X11_EVENT_MASK_KEY_PRESS: u32 = 1 << 0;
 const LINUX_X11_EVENT_MASK_KEY_RELEASE: u32 = 1 << 1;
@@ -1351,6 +1428,7 @@ struct LinuxShimState {
     brk_base: u64,
     brk_current: u64,
     brk_limit: u64,
+    mmap_base: u64,
     mmap_cursor: u64,
     mmap_count: usize,
     write_calls: u64,
@@ -1399,6 +1477,8 @@ struct LinuxShimState {
     exec_transition_pending: bool,
     stdio_line: [u8; ui::TERM_MAX_INPUT],
     stdio_line_len: usize,
+    /// fd (1 or 2) the buffered `stdio_line` was written to.
+    stdio_fd: u32,
     maps: [LinuxMmapSlot; LINUX_MAX_MMAPS],
     runtime_files: [LinuxRuntimeFileSlot; LINUX_MAX_RUNTIME_FILES],
     dirs: [LinuxDirSlot; LINUX_MAX_DIR_SLOTS],
@@ -1414,6 +1494,8 @@ struct LinuxShimState {
     x11_gcs: [LinuxX11GcSlot; LINUX_X11_MAX_GCS],
     x11_shm_segments: [LinuxX11ShmSlot; LINUX_X11_MAX_SHM_SEGMENTS],
     x11_focus_window: u32,
+    /// `gui::clipboard::revision` the X server last synced with.
+    x11_clipboard_revision: u64,
     x11_pointer_x: i16,
     x11_pointer_y: i16,
     x11_pointer_buttons: u8,
@@ -1437,6 +1519,7 @@ impl LinuxShimState {
             brk_base: 0,
             brk_current: 0,
             brk_limit: 0,
+            mmap_base: LINUX_MMAP_BASE,
             mmap_cursor: LINUX_MMAP_BASE,
             mmap_count: 0,
             write_calls: 0,
@@ -1485,6 +1568,7 @@ impl LinuxShimState {
             exec_transition_pending: false,
             stdio_line: [0; ui::TERM_MAX_INPUT],
             stdio_line_len: 0,
+            stdio_fd: 1,
             maps: [LinuxMmapSlot::empty(); LINUX_MAX_MMAPS],
             runtime_files: [LinuxRuntimeFileSlot::empty(); LINUX_MAX_RUNTIME_FILES],
             dirs: [LinuxDirSlot::empty(); LINUX_MAX_DIR_SLOTS],
@@ -1500,6 +1584,7 @@ impl LinuxShimState {
             x11_gcs: [LinuxX11GcSlot::empty(); LINUX_X11_MAX_GCS],
             x11_shm_segments: [LinuxX11ShmSlot::empty(); LINUX_X11_MAX_SHM_SEGMENTS],
             x11_focus_window: LINUX_X11_ROOT_WINDOW,
+            x11_clipboard_revision: 0,
             x11_pointer_x: 0,
             x11_pointer_y: 0,
             x11_pointer_buttons: 0,
@@ -1733,14 +1818,12 @@ fn handle_write_line(_thread_index: usize, a0: u64, a1: u64, _a2: u64, _a3: u64)
 
     let requested = (a1 as usize).min(ui::TERM_MAX_INPUT);
     let mut buf = [0u8; ui::TERM_MAX_INPUT];
-
-    unsafe {
-        let src = a0 as *const u8;
-        let mut i = 0usize;
-        while i < requested {
-            let b = ptr::read(src.add(i));
-            buf[i] = if b.is_ascii() && (b >= 0x20 || b == b'\t') { b } else { b'?' };
-            i += 1;
+    if crate::uaccess::copy_from_user(&mut buf[..requested], a0).is_err() {
+        return 0;
+    }
+    for b in buf[..requested].iter_mut() {
+        if !(b.is_ascii() && (*b >= 0x20 || *b == b'\t')) {
+            *b = b'?';
         }
     }
 
@@ -1783,13 +1866,8 @@ fn handle_recv_command(_thread_index: usize, a0: u64, a1: u64, _a2: u64, _a3: u6
     }
 
     let copy = n.min(cap);
-    unsafe {
-        let dst = a0 as *mut u8;
-        let mut i = 0usize;
-        while i < copy {
-            ptr::write(dst.add(i), local[i]);
-            i += 1;
-        }
+    if crate::uaccess::copy_to_user(a0, &local[..copy]).is_err() {
+        return 0;
     }
 
     copy as u64
@@ -1820,9 +1898,8 @@ fn handle_thread_info(_thread_index: usize, a0: u64, a1: u64, _a2: u64, _a3: u64
         name: info.name,
     };
 
-    unsafe {
-        let dst = a1 as *mut SysThreadInfo;
-        ptr::write(dst, out);
+    if crate::uaccess::write_user(a1, out).is_err() {
+        return 0;
     }
 
     1
@@ -1852,6 +1929,332 @@ fn handle_priv_unsafe_test(_thread_index: usize, _a0: u64, _a1: u64, _a2: u64, _
     }
 }
 
+fn http_owner(thread_index: usize) -> String {
+    if thread_index == LINUX_ELF_THREAD {
+        return alloc::format!("linux{}", unsafe { LINUX_SHIM.current_pid });
+    }
+    match process::thread_info(thread_index) {
+        Some(info) => {
+            let len = (info.name_len as usize).min(info.name.len());
+            String::from_utf8_lossy(&info.name[..len]).into_owned()
+        }
+        None => alloc::format!("thread{}", thread_index),
+    }
+}
+
+fn http_error_code(err: crate::net::redux_http::HttpApiError) -> u64 {
+    use crate::net::redux_http::HttpApiError;
+    match err {
+        HttpApiError::BadHandle => SYS_ERR_BAD_HANDLE,
+        HttpApiError::Invalid | HttpApiError::Busy => SYS_ERR_INVALID,
+        HttpApiError::Unsupported => SYS_ERR_UNSUPPORTED,
+        HttpApiError::Denied => SYS_ERR_DENIED,
+        HttpApiError::Quota => SYS_ERR_QUOTA,
+        HttpApiError::Network => SYS_ERR_NET,
+    }
+}
+
+/// Copy a user buffer of at most `max` bytes; `None` when it is missing or
+/// too long.
+fn http_user_bytes(ptr_raw: u64, len: u64, max: usize) -> Option<Vec<u8>> {
+    let len = len as usize;
+    if len > max || (ptr_raw == 0 && len != 0) {
+        return None;
+    }
+    let mut out = alloc::vec![0u8; len];
+    crate::uaccess::copy_from_user(&mut out, ptr_raw).ok()?;
+    Some(out)
+}
+
+fn http_user_str(ptr_raw: u64, len: u64) -> Option<String> {
+    String::from_utf8(http_user_bytes(ptr_raw, len, SYS_HTTP_MAX_ARG)?).ok()
+}
+
+// a0/a1 = method, a2/a3 = URL. Returns a request handle.
+fn handle_http_open(thread_index: usize, a0: u64, a1: u64, a2: u64, a3: u64) -> u64 {
+    let (method, url) = match (http_user_str(a0, a1), http_user_str(a2, a3)) {
+        (Some(m), Some(u)) => (m, u),
+        _ => return SYS_ERR_INVALID,
+    };
+    let owner = http_owner(thread_index);
+    match crate::net::redux_http::open(owner.as_str(), method.as_str(), url.as_str()) {
+        Ok(id) => id as u64,
+        Err(e) => http_error_code(e),
+    }
+}
+
+// a0 = handle, a1/a2 = "Name: value".
+fn handle_http_set_header(thread_index: usize, a0: u64, a1: u64, a2: u64, _a3: u64) -> u64 {
+    let line = match http_user_str(a1, a2) {
+        Some(l) => l,
+        None => return SYS_ERR_INVALID,
+    };
+    let (name, value) = match line.split_once(':') {
+        Some(pair) => pair,
+        None => return SYS_ERR_INVALID,
+    };
+    let owner = http_owner(thread_index);
+    match crate::net::redux_http::set_header(a0 as u32, owner.as_str(), name, value) {
+        Ok(()) => 0,
+        Err(e) => http_error_code(e),
+    }
+}
+
+// a0 = handle, a1/a2 = request body. Blocks until the response is buffered;
+// returns the HTTP status.
+fn handle_http_send(thread_index: usize, a0: u64, a1: u64, a2: u64, _a3: u64) -> u64 {
+    let body = match http_user_bytes(a1, a2, crate::net::redux_http::REDUX_HTTP_MAX_BODY) {
+        Some(b) => b,
+        None => return SYS_ERR_INVALID,
+    };
+    let owner = http_owner(thread_index);
+    match crate::net::redux_http::send(a0 as u32, owner.as_str(), body.as_slice()) {
+        Ok(status) => status as u64,
+        Err(e) => http_error_code(e),
+    }
+}
+
+// a0 = handle, a1/a2 = output buffer. Copies up to a2 bytes of
+// "name: value\n" lines and returns the full length.
+fn handle_http_headers(thread_index: usize, a0: u64, a1: u64, a2: u64, _a3: u64) -> u64 {
+    let owner = http_owner(thread_index);
+    let text = match crate::net::redux_http::headers_text(a0 as u32, owner.as_str()) {
+        Ok(t) => t,
+        Err(e) => return http_error_code(e),
+    };
+    if a1 != 0 {
+        let n = text.len().min(a2 as usize);
+        if crate::uaccess::copy_to_user(a1, &text.as_bytes()[..n]).is_err() {
+            return SYS_ERR_INVALID;
+        }
+    }
+    text.len() as u64
+}
+
+// a0 = handle, a1/a2 = output buffer. Returns bytes read, 0 at end of body.
+fn handle_http_read(thread_index: usize, a0: u64, a1: u64, a2: u64, _a3: u64) -> u64 {
+    if a1 == 0 {
+        return SYS_ERR_INVALID;
+    }
+    if crate::uaccess::check(a1, a2 as usize).is_err() {
+        return SYS_ERR_INVALID;
+    }
+    let owner = http_owner(thread_index);
+    let mut buf = alloc::vec![0u8; a2 as usize];
+    match crate::net::redux_http::read(a0 as u32, owner.as_str(), &mut buf) {
+        Ok(n) => match crate::uaccess::copy_to_user(a1, &buf[..n]) {
+            Ok(()) => n as u64,
+            Err(_) => SYS_ERR_INVALID,
+        },
+        Err(e) => http_error_code(e),
+    }
+}
+
+// a0 = handle, a1 = option (1 keep-alive ms, 2 no-delay, 3 timeout ms),
+// a2 = value. Only before SYS_HTTP_SEND.
+fn handle_http_set_option(thread_index: usize, a0: u64, a1: u64, a2: u64, _a3: u64) -> u64 {
+    let option = match crate::net::redux_http::HttpOption::from_raw(a1) {
+        Some(o) => o,
+        None => return SYS_ERR_INVALID,
+    };
+    let owner = http_owner(thread_index);
+    match crate::net::redux_http::set_option(a0 as u32, owner.as_str(), option, a2) {
+        Ok(()) => 0,
+        Err(e) => http_error_code(e),
+    }
+}
+
+fn handle_http_close(thread_index: usize, a0: u64, _a1: u64, _a2: u64, _a3: u64) -> u64 {
+    let owner = http_owner(thread_index);
+    match crate::net::redux_http::close(a0 as u32, owner.as_str()) {
+        Ok(()) => 0,
+        Err(e) => http_error_code(e),
+    }
+}
+
+// a0/a1 = key, a2/a3 = output buffer. Copies up to a3 bytes of the value
+// as text and returns its full length.
+fn handle_config_get(_thread_index: usize, a0: u64, a1: u64, a2: u64, a3: u64) -> u64 {
+    let key = match http_user_str(a0, a1) {
+        Some(k) if crate::config::valid_key(k.as_str()) => k,
+        _ => return SYS_ERR_INVALID,
+    };
+    let text = match crate::config::get(key.as_str()) {
+        Some(value) => value.to_text(),
+        None => return SYS_ERR_NOT_FOUND,
+    };
+    if a2 != 0 {
+        let n = text.len().min(a3 as usize);
+        if crate::uaccess::copy_to_user(a2, &text.as_bytes()[..n]).is_err() {
+            return SYS_ERR_INVALID;
+        }
+    }
+    text.len() as u64
+}
+
+// a0/a1 = key, a2/a3 = value text (typed like `config set`). A zero-length
+// value removes the key.
+fn handle_config_set(thread_index: usize, a0: u64, a1: u64, a2: u64, a3: u64) -> u64 {
+    let (key, text) = match (http_user_str(a0, a1), http_user_str(a2, a3)) {
+        (Some(k), Some(v)) if crate::config::valid_key(k.as_str()) && v.len() <= crate::config::VALUE_MAX_BYTES => {
+            (k, v)
+        }
+        _ => return SYS_ERR_INVALID,
+    };
+    if key.starts_with(SYS_CONFIG_PROTECTED_PREFIX) {
+        crate::klog::log(
+            crate::klog::Level::Warning,
+            alloc::format!("config: {} no puede cambiar {}", http_owner(thread_index), key).as_str(),
+        );
+        return SYS_ERR_PERMISSION;
+    }
+    let result = if text.is_empty() {
+        crate::config::unset(key.as_str()).map(|_| ())
+    } else {
+        crate::config::set(key.as_str(), crate::config::ConfigValue::parse(text.as_str()))
+    };
+    // The change is applied in memory even when writing the journal fails.
+    if let Err(e) = result {
+        crate::klog::log(crate::klog::Level::Warning, alloc::format!("config: {}", e).as_str());
+    }
+    0
+}
+
+// a0/a1 = key prefix. Returns the sequence number of the newest change under
+// it (0 if none); apps poll and compare with the last value they saw.
+fn handle_config_watch(_thread_index: usize, a0: u64, a1: u64, _a2: u64, _a3: u64) -> u64 {
+    match http_user_str(a0, a1) {
+        Some(prefix) => crate::config::revision(prefix.as_str()),
+        None => SYS_ERR_INVALID,
+    }
+}
+
+// a0/a1 = output buffer. Fills up to SYS_GET_RANDOM_MAX bytes from the kernel
+// generator and returns how many.
+fn handle_get_random(_thread_index: usize, a0: u64, a1: u64, _a2: u64, _a3: u64) -> u64 {
+    if a0 == 0 {
+        return SYS_ERR_INVALID;
+    }
+    let len = (a1 as usize).min(SYS_GET_RANDOM_MAX);
+    let mut buf = [0u8; SYS_GET_RANDOM_MAX];
+    crate::random::fill(&mut buf[..len]);
+    if crate::uaccess::copy_to_user(a0, &buf[..len]).is_err() {
+        return SYS_ERR_INVALID;
+    }
+    len as u64
+}
+
+// a0/a1 = folder path from the root of the mounted volume; empty or "/"
+// watches the whole volume. The session user needs to be able to list it.
+// Returns a watch descriptor for SYS_FS_READ_EVENTS.
+fn handle_fs_watch(thread_index: usize, a0: u64, a1: u64, _a2: u64, _a3: u64) -> u64 {
+    let Some(path) = http_user_str(a0, a1) else {
+        return SYS_ERR_INVALID;
+    };
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    if fat.init_status != crate::fat32::InitStatus::Success {
+        return SYS_ERR_UNSUPPORTED;
+    }
+    let path = path.replace('\\', "/");
+    let dir = if path.trim().trim_matches('/').is_empty() {
+        None
+    } else {
+        match fat.resolve_path(fat.root_cluster, path.as_str()) {
+            Ok((_, cluster)) if cluster >= 2 => Some(cluster),
+            Ok(_) => Some(fat.root_cluster),
+            Err(_) => return SYS_ERR_NOT_FOUND,
+        }
+    };
+    let _acting = crate::perm::act_as(crate::perm::session());
+    if crate::perm::authorize_list(fat, dir.unwrap_or(fat.root_cluster)).is_err() {
+        return SYS_ERR_PERMISSION;
+    }
+    let owner = http_owner(thread_index);
+    match crate::fswatch::add_watch(owner.as_str(), fat.volume_id(), dir) {
+        Ok(id) => id as u64,
+        Err(_) => SYS_ERR_QUOTA,
+    }
+}
+
+// a0 = watch descriptor, a1/a2 = output buffer. Fills it with whole event
+// records ([u8 change][u8 0][u16 name length][u32 cookie][name], little
+// endian) and returns the bytes written; 0 when nothing is queued.
+fn handle_fs_read_events(thread_index: usize, a0: u64, a1: u64, a2: u64, _a3: u64) -> u64 {
+    let len = (a2 as usize).min(SYS_FS_EVENTS_MAX);
+    if a1 == 0 || crate::uaccess::check(a1, len).is_err() {
+        return SYS_ERR_INVALID;
+    }
+    let owner = http_owner(thread_index);
+    let records = match crate::fswatch::read_records(owner.as_str(), a0 as u32, len) {
+        Ok(records) => records,
+        Err(_) => return SYS_ERR_BAD_HANDLE,
+    };
+    if crate::uaccess::copy_to_user(a1, records.as_slice()).is_err() {
+        return SYS_ERR_INVALID;
+    }
+    records.len() as u64
+}
+
+// a0 = watch descriptor.
+fn handle_fs_unwatch(thread_index: usize, a0: u64, _a1: u64, _a2: u64, _a3: u64) -> u64 {
+    let owner = http_owner(thread_index);
+    match crate::fswatch::remove_watch(owner.as_str(), a0 as u32) {
+        Ok(()) => 0,
+        Err(_) => SYS_ERR_BAD_HANDLE,
+    }
+}
+
+// Opens a microphone reader; returns its handle. Audio starts at the moment
+// of the call, mono 16-bit at `capture::RATE`.
+fn handle_audio_capture_open(thread_index: usize, _a0: u64, _a1: u64, _a2: u64, _a3: u64) -> u64 {
+    if !crate::capture::available() {
+        return SYS_ERR_UNSUPPORTED;
+    }
+    let owner = http_owner(thread_index);
+    match crate::capture::open(owner.as_str()) {
+        Ok(id) => {
+            crate::gui::notifications::post(
+                "Microfono",
+                "Microfono en uso",
+                owner.as_str(),
+                crate::gui::notifications::Urgency::Info,
+            );
+            id as u64
+        }
+        Err(_) => SYS_ERR_QUOTA,
+    }
+}
+
+// a0 = capture handle, a1/a2 = output buffer. Fills it with little endian
+// i16 samples captured since the last read and returns the bytes written;
+// 0 when nothing new arrived.
+fn handle_audio_capture_read(thread_index: usize, a0: u64, a1: u64, a2: u64, _a3: u64) -> u64 {
+    let len = (a2 as usize).min(SYS_AUDIO_CAPTURE_READ_MAX) & !1;
+    if a1 == 0 || crate::uaccess::check(a1, len).is_err() {
+        return SYS_ERR_INVALID;
+    }
+    let owner = http_owner(thread_index);
+    let mut samples = alloc::vec![0i16; len / 2];
+    let count = match crate::capture::read(owner.as_str(), a0 as u32, samples.as_mut_slice()) {
+        Ok(count) => count,
+        Err(_) => return SYS_ERR_BAD_HANDLE,
+    };
+    let bytes: Vec<u8> = samples[..count].iter().flat_map(|s| s.to_le_bytes()).collect();
+    if crate::uaccess::copy_to_user(a1, bytes.as_slice()).is_err() {
+        return SYS_ERR_INVALID;
+    }
+    bytes.len() as u64
+}
+
+// a0 = capture handle.
+fn handle_audio_capture_close(thread_index: usize, a0: u64, _a1: u64, _a2: u64, _a3: u64) -> u64 {
+    let owner = http_owner(thread_index);
+    match crate::capture::close(owner.as_str(), a0 as u32) {
+        Ok(()) => 0,
+        Err(_) => SYS_ERR_BAD_HANDLE,
+    }
+}
+
 fn linux_align_up(value: u64, align: u64) -> Option<u64> {
     if align == 0 {
         return Some(value);
@@ -1959,16 +2362,6 @@ struct LinuxOpenHow {
     resolve: u64,
 }
 
-impl LinuxOpenHow {
-    const fn empty() -> Self {
-        Self {
-            flags: 0,
-            mode: 0,
-            resolve: 0,
-        }
-    }
-}
-
 #[repr(C)]
 #[derive(Clone, Copy)]
 struct LinuxTimeval {
@@ -2013,6 +2406,7 @@ struct LinuxUcred {
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 struct LinuxTimezone {
     tz_minuteswest: i32,
     tz_dsttime: i32,
@@ -2027,6 +2421,7 @@ struct LinuxPollFd {
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 struct LinuxRlimit {
     rlim_cur: u64,
     rlim_max: u64,
@@ -2111,6 +2506,7 @@ struct LinuxStackT {
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 struct LinuxStat64 {
     st_dev: u64,
     st_ino: u64,
@@ -2355,18 +2751,10 @@ fn linux_read_c_string(path_ptr: u64, out: &mut [u8; LINUX_PATH_MAX]) -> Result<
         return Err(linux_neg_errno(14)); // EFAULT
     }
     let mut raw = [0u8; LINUX_PATH_MAX];
-    let mut n = 0usize;
-    unsafe {
-        let src = path_ptr as *const u8;
-        while n < raw.len() {
-            let b = ptr::read(src.add(n));
-            if b == 0 {
-                break;
-            }
-            raw[n] = b;
-            n += 1;
-        }
-    }
+    let n = match crate::uaccess::copy_cstr_from_user(path_ptr, &mut raw) {
+        Ok(n) => n,
+        Err(_) => return Err(linux_neg_errno(14)), // EFAULT
+    };
     if n == raw.len() {
         return Err(linux_neg_errno(36)); // ENAMETOOLONG
     }
@@ -2381,18 +2769,10 @@ fn linux_read_raw_c_string(ptr_raw: u64, out: &mut [u8]) -> Result<usize, i64> {
     if ptr_raw == 0 {
         return Err(linux_neg_errno(14)); // EFAULT
     }
-    let mut n = 0usize;
-    unsafe {
-        let src = ptr_raw as *const u8;
-        while n < out.len() {
-            let b = ptr::read(src.add(n));
-            if b == 0 {
-                break;
-            }
-            out[n] = b;
-            n += 1;
-        }
-    }
+    let n = match crate::uaccess::copy_cstr_from_user(ptr_raw, out) {
+        Ok(n) => n,
+        Err(_) => return Err(linux_neg_errno(14)), // EFAULT
+    };
     if n == out.len() {
         return Err(linux_neg_errno(36)); // ENAMETOOLONG
     }
@@ -2574,7 +2954,7 @@ fn linux_fat_lookup_absolute_path(path: &[u8], path_len: usize) -> Option<LinuxF
     }
 
     unsafe {
-        let fat = &mut crate::fat32::GLOBAL_FAT;
+        let fat = crate::fat32::GLOBAL_FAT.get_mut();
         if fat.init_status != crate::fat32::InitStatus::Success || fat.root_cluster < 2 {
             return None;
         }
@@ -2907,7 +3287,7 @@ fn linux_fat_read_file_bytes(cluster: u32, file_size: usize) -> Result<Vec<u8>,
     }
 
     unsafe {
-        let fat = &mut crate::fat32::GLOBAL_FAT;
+        let fat = crate::fat32::GLOBAL_FAT.get_mut();
         if fat.init_status != crate::fat32::InitStatus::Success || fat.root_cluster < 2 {
             return Err(linux_neg_errno(2)); // ENOENT
         }
@@ -3132,13 +3512,24 @@ fn linux_resolve_dirfd_base_path(
     out: &mut [u8; LINUX_PATH_MAX],
 ) -> Result<usize, i64> {
     if dirfd == LINUX_AT_FDCWD {
-        let prefix = b"/LINUXRT/";
-        let mut i = 0usize;
-        while i < prefix.len() {
-            out[i] = prefix[i];
-            i += 1;
-        }
-        return Ok(prefix.len());
+        // The working directory lives in the launcher's process environment;
+        // the guest root is /LINUXRT on the volume.
+        let len = crate::procenv::with_cwd(|cwd| {
+            let mut n = 0usize;
+            for &byte in b"/LINUXRT".iter().chain(cwd.as_bytes().iter()) {
+                if n + 1 >= LINUX_PATH_MAX {
+                    break;
+                }
+                out[n] = byte;
+                n += 1;
+            }
+            if out[n - 1] != b'/' {
+                out[n] = b'/';
+                n += 1;
+            }
+            n
+        });
+        return Ok(len);
     }
     let slot = linux_lookup_open_slot(state, dirfd as i32)?;
     if slot.kind != LINUX_OPEN_KIND_DIR {
@@ -3273,20 +3664,13 @@ fn linux_vfs_emit_dirent64(
     if offset.checked_add(reclen)? > count {
         return None;
     }
-    unsafe {
-        let ptr_base = (dirp as usize).checked_add(offset)? as *mut u8;
-        ptr::write(ptr_base as *mut u64, ino);
-        ptr::write(ptr_base.add(8) as *mut i64, next_off as i64);
-        ptr::write(ptr_base.add(16) as *mut u16, reclen as u16);
-        ptr::write(ptr_base.add(18), d_type);
-        ptr::copy_nonoverlapping(name_bytes.as_ptr(), ptr_base.add(19), name_bytes.len());
-        ptr::write(ptr_base.add(19 + name_bytes.len()), 0);
-        let mut pad = 20 + name_bytes.len();
-        while pad < reclen {
-            ptr::write(ptr_base.add(pad), 0);
-            pad += 1;
-        }
-    }
+    let mut record = alloc::vec![0u8; reclen];
+    record[0..8].copy_from_slice(&ino.to_le_bytes());
+    record[8..16].copy_from_slice(&(next_off as i64).to_le_bytes());
+    record[16..18].copy_from_slice(&(reclen as u16).to_le_bytes());
+    record[18] = d_type;
+    record[19..19 + name_bytes.len()].copy_from_slice(name_bytes);
+    crate::uaccess::copy_to_user(dirp.checked_add(offset as u64)?, &record).ok()?;
     Some(reclen)
 }
 
@@ -3670,8 +4054,11 @@ fn linux_sendmsg_collect_scm_rights(
     let mut count = 0usize;
 
     while offset + header_len <= total_len {
-        let header_ptr = msg.msg_control.saturating_add(offset as u64) as *const LinuxCmsgHdr;
-        let header = unsafe { ptr::read(header_ptr) };
+        let header_addr = msg.msg_control.saturating_add(offset as u64);
+        let Ok(header) = crate::uaccess::read_user::<LinuxCmsgHdr>(header_addr) else {
+            linux_socket_rights_release_open_slots(state, out_slots, count);
+            return Err(linux_neg_errno(14)); // EFAULT
+        };
         let cmsg_len = header.cmsg_len as usize;
         if cmsg_len < header_len || offset.saturating_add(cmsg_len) > total_len {
             linux_socket_rights_release_open_slots(state, out_slots, count);
@@ -3691,11 +4078,13 @@ fn linux_sendmsg_collect_scm_rights(
                     linux_socket_rights_release_open_slots(state, out_slots, count);
                     return Err(linux_neg_errno(22)); // EINVAL
                 }
-                let fd_ptr = msg
+                let fd_addr = msg
                     .msg_control
-                    .saturating_add((offset + header_len + i * core::mem::size_of::<i32>()) as u64)
-                    as *const i32;
-                let passed_fd = unsafe { ptr::read(fd_ptr) };
+                    .saturating_add((offset + header_len + i * core::mem::size_of::<i32>()) as u64);
+                let Ok(passed_fd) = crate::uaccess::read_user::<i32>(fd_addr) else {
+                    linux_socket_rights_release_open_slots(state, out_slots, count);
+                    return Err(linux_neg_errno(14)); // EFAULT
+                };
                 let open_idx = match linux_hold_runtime_fd_for_scm_rights(state, passed_fd) {
                     Ok(v) => v,
                     Err(err) => {
@@ -3737,6 +4126,30 @@ fn linux_socket_peer_for_rights(state: &LinuxShimState, sock_idx: usize) -> Resu
     Ok(peer_idx)
 }
 
+/// Write one SCM_RIGHTS control message carrying `fds` into `msg.msg_control`.
+/// When the buffer cannot be written the descriptors are closed again and
+/// `msg_controllen` is left at zero.
+fn linux_recvmsg_write_scm_rights(state: &mut LinuxShimState, msg: &mut LinuxMsgHdr, fds: &[i32]) {
+    let header_len = core::mem::size_of::<LinuxCmsgHdr>();
+    let cmsg_len = header_len + fds.len() * core::mem::size_of::<i32>();
+    let mut record = alloc::vec![0u8; cmsg_len];
+    record[0..8].copy_from_slice(&(cmsg_len as u64).to_le_bytes());
+    record[8..12].copy_from_slice(&(LINUX_SOL_SOCKET as i32).to_le_bytes());
+    record[12..16].copy_from_slice(&(LINUX_SCM_RIGHTS as i32).to_le_bytes());
+    for (j, fd) in fds.iter().enumerate() {
+        let at = header_len + j * core::mem::size_of::<i32>();
+        record[at..at + 4].copy_from_slice(&fd.to_le_bytes());
+    }
+    if crate::uaccess::copy_to_user(msg.msg_control, &record).is_ok() {
+        msg.msg_controllen = linux_cmsg_align(cmsg_len) as u64;
+        return;
+    }
+    for &fd in fds {
+        let _ = linux_sys_close(state, fd as u64);
+    }
+    msg.msg_controllen = 0;
+}
+
 fn linux_recvmsg_attach_scm_rights(
     state: &mut LinuxShimState,
     sock_idx: usize,
@@ -3795,24 +4208,10 @@ fn linux_recvmsg_attach_scm_rights(
     }
 
     if emitted_count > 0 && control_ptr != 0 && control_len >= header_len {
-        let cmsg_len = header_len + emitted_count * core::mem::size_of::<i32>();
-        let cmsg = LinuxCmsgHdr {
-            cmsg_len: cmsg_len as u64,
-            cmsg_level: LINUX_SOL_SOCKET as i32,
-            cmsg_type: LINUX_SCM_RIGHTS as i32,
-        };
-        unsafe {
-            ptr::write(control_ptr as *mut LinuxCmsgHdr, cmsg);
-            let mut j = 0usize;
-            while j < emitted_count {
-                let dst = control_ptr
-                    .saturating_add((header_len + j * core::mem::size_of::<i32>()) as u64)
-                    as *mut i32;
-                ptr::write(dst, emitted[j]);
-                j += 1;
-            }
+        linux_recvmsg_write_scm_rights(state, msg, &emitted[..emitted_count]);
+        if msg.msg_controllen == 0 {
+            truncated = true;
         }
-        msg.msg_controllen = linux_cmsg_align(cmsg_len) as u64;
     } else {
         msg.msg_controllen = 0;
         if total_rights > 0 {
@@ -3883,24 +4282,10 @@ fn linux_recvmsg_attach_wayland_event_fds(
     }
 
     if emitted_count > 0 && control_ptr != 0 && control_len >= header_len {
-        let cmsg_len = header_len + emitted_count * core::mem::size_of::<i32>();
-        let cmsg = LinuxCmsgHdr {
-            cmsg_len: cmsg_len as u64,
-            cmsg_level: LINUX_SOL_SOCKET as i32,
-            cmsg_type: LINUX_SCM_RIGHTS as i32,
-        };
-        unsafe {
-            ptr::write(control_ptr as *mut LinuxCmsgHdr, cmsg);
-            let mut j = 0usize;
-            while j < emitted_count {
-                let dst = control_ptr
-                    .saturating_add((header_len + j * core::mem::size_of::<i32>()) as u64)
-                    as *mut i32;
-                ptr::write(dst, emitted[j]);
-                j += 1;
-            }
+        linux_recvmsg_write_scm_rights(state, msg, &emitted[..emitted_count]);
+        if msg.msg_controllen == 0 {
+            truncated = true;
         }
-        msg.msg_controllen = linux_cmsg_align(cmsg_len) as u64;
     } else {
         msg.msg_controllen = 0;
         if total_rights > 0 {
@@ -6183,6 +6568,8 @@ fn linux_x11_known_atom(name: &[u8]) -> u32 {
         LINUX_X11_ATOM_TARGETS
     } else if linux_ascii_eq_ignore_case(name, b"_MOTIF_WM_HINTS") {
         LINUX_X11_ATOM_MOTIF_WM_HINTS
+    } else if linux_ascii_eq_ignore_case(name, b"_REDUX_CLIPBOARD") {
+        LINUX_X11_ATOM_REDUX_CLIPBOARD
     } else if linux_ascii_eq_ignore_case(name, b"ATOM") {
         LINUX_X11_ATOM_ATOM
     } else if linux_ascii_eq_ignore_case(name, b"WINDOW") {
@@ -6236,6 +6623,7 @@ fn linux_x11_atom_name_known(atom: u32) -> &'static [u8] {
         LINUX_X11_ATOM_CLIPBOARD => b"CLIPBOARD",
         LINUX_X11_ATOM_TARGETS => b"TARGETS",
         LINUX_X11_ATOM_MOTIF_WM_HINTS => b"_MOTIF_WM_HINTS",
+        LINUX_X11_ATOM_REDUX_CLIPBOARD => b"_REDUX_CLIPBOARD",
         _ => b"",
     }
 }
@@ -6687,6 +7075,7 @@ fn linux_x11_reset_server(state: &mut LinuxShimState) {
     state.x11_windows = [LinuxX11WindowSlot::empty(); LINUX_X11_MAX_WINDOWS];
     state.x11_properties = [LinuxX11PropertySlot::empty(); LINUX_X11_MAX_PROPERTIES];
     state.x11_selections = [LinuxX11SelectionSlot::empty(); LINUX_X11_MAX_SELECTIONS];
+    state.x11_clipboard_revision = 0;
     state.x11_pixmaps = [LinuxX11PixmapSlot::empty(); LINUX_X11_MAX_PIXMAPS];
     state.x11_gcs = [LinuxX11GcSlot::empty(); LINUX_X11_MAX_GCS];
     state.x11_focus_window = LINUX_X11_ROOT_WINDOW;
@@ -7057,6 +7446,58 @@ fn linux_x11_get_selection_owner(state: &LinuxShimState, selection_atom: u32) ->
     }
 }
 
+fn linux_x11_queue_selection_request(
+    state: &mut LinuxShimState,
+    sock_idx: usize,
+    owner: u32,
+    requestor: u32,
+    selection: u32,
+    target: u32,
+    property: u32,
+) {
+    let little = linux_x11_little(&state.sockets[sock_idx]);
+    let mut ev = [0u8; 28];
+    linux_write_u32_order(&mut ev, 0, timer::ticks() as u32, little);
+    linux_write_u32_order(&mut ev, 4, owner, little);
+    linux_write_u32_order(&mut ev, 8, requestor, little);
+    linux_write_u32_order(&mut ev, 12, selection, little);
+    linux_write_u32_order(&mut ev, 16, target, little);
+    linux_write_u32_order(&mut ev, 20, property, little);
+    linux_x11_queue_window_event(state, sock_idx, owner, LINUX_X11_EVENT_SELECTION_REQUEST, 0, 0, &ev);
+}
+
+/// A copy in a native window takes CLIPBOARD away from the Linux app that
+/// owned it, so its next paste asks the server for the desktop clipboard.
+fn linux_x11_sync_clipboard(state: &mut LinuxShimState, sock_idx: usize) {
+    let revision = crate::gui::clipboard::revision();
+    if revision == state.x11_clipboard_revision {
+        return;
+    }
+    state.x11_clipboard_revision = revision;
+    let owner = linux_x11_get_selection_owner(state, LINUX_X11_ATOM_CLIPBOARD);
+    if owner == 0 {
+        return;
+    }
+    linux_x11_set_selection_owner(state, LINUX_X11_ATOM_CLIPBOARD, 0);
+    let little = linux_x11_little(&state.sockets[sock_idx]);
+    let mut ev = [0u8; 28];
+    linux_write_u32_order(&mut ev, 0, timer::ticks() as u32, little);
+    linux_write_u32_order(&mut ev, 4, owner, little);
+    linux_write_u32_order(&mut ev, 8, LINUX_X11_ATOM_CLIPBOARD, little);
+    linux_x11_queue_window_event(state, sock_idx, owner, LINUX_X11_EVENT_SELECTION_CLEAR, 0, 0, &ev);
+}
+
+/// The owner's answer to the request `SetSelectionOwner` sent: publish it
+/// as the desktop clipboard without clearing the owner again.
+fn linux_x11_take_clipboard(state: &mut LinuxShimState, data: &[u8]) {
+    if data.is_empty() {
+        return;
+    }
+    let text = String::from_utf8_lossy(data).into_owned();
+    crate::gui::clipboard::set(crate::gui::clipboard::Payload::Text(text));
+    state.x11_clipboard_revision = crate::gui::clipboard::revision();
+}
+
 fn linux_x11_collect_children(state: &LinuxShimState, parent: u32, out: &mut [u32]) -> usize {
     let mut count = 0usize;
     let mut i = 0usize;
@@ -7625,8 +8066,8 @@ fn linux_x11_handle_extension_request(
                                 if sx >= total_w { break; }
 
                                 let pixel_offset = offset + (sy * total_w + sx) * 4;
-                                let color = unsafe { 
-                                    core::ptr::read_volatile((shm_ptr + pixel_offset as u64) as *const u32) 
+                                let Ok(color) = crate::uaccess::read_user::<u32>(shm_ptr + pixel_offset as u64) else {
+                                    break;
                                 };
                                 
                                 linux_x11_drawable_set_pixel(
@@ -7755,6 +8196,7 @@ fn linux_x11_handle_request(state: &mut LinuxShimState, sock_idx: usize, opcode:
         return;
     }
     linux_x11_ensure_root_window(state);
+    linux_x11_sync_clipboard(state, sock_idx);
 
     match opcode {
         1 => {
@@ -8161,7 +8603,12 @@ fn linux_x11_handle_request(state: &mut LinuxShimState, sock_idx: usize, opcode:
                 let data_len_units = linux_read_u32_order(req, 20, little) as usize;
                 let bpp = linux_x11_property_bytes_per(format);
                 let data_bytes = data_len_units.saturating_mul(bpp);
-                if req.len() >= 24 + data_bytes && data_bytes <= LINUX_X11_PROPERTY_DATA_MAX {
+                if window == LINUX_X11_ROOT_WINDOW && property == LINUX_X11_ATOM_REDUX_CLIPBOARD {
+                    // Not stored: it may exceed a property slot.
+                    if format == 8 && req.len() >= 24 + data_bytes {
+                        linux_x11_take_clipboard(state, &req[24..24 + data_bytes]);
+                    }
+                } else if req.len() >= 24 + data_bytes && data_bytes <= LINUX_X11_PROPERTY_DATA_MAX {
                     linux_x11_set_property(
                         state,
                         window,
@@ -8294,6 +8741,18 @@ fn linux_x11_handle_request(state: &mut LinuxShimState, sock_idx: usize, opcode:
                 let owner = linux_read_u32_order(req, 4, little);
                 let selection = linux_read_u32_order(req, 8, little);
                 linux_x11_set_selection_owner(state, selection, owner);
+                if selection == LINUX_X11_ATOM_CLIPBOARD && owner != 0 {
+                    // Copy what the app just put on CLIPBOARD to the desktop clipboard.
+                    linux_x11_queue_selection_request(
+                        state,
+                        sock_idx,
+                        owner,
+                        LINUX_X11_ROOT_WINDOW,
+                        selection,
+                        LINUX_X11_ATOM_UTF8_STRING,
+                        LINUX_X11_ATOM_REDUX_CLIPBOARD,
+                    );
+                }
             }
         }
         23 => {
@@ -8313,6 +8772,16 @@ fn linux_x11_handle_request(state: &mut LinuxShimState, sock_idx: usize, opcode:
                 let selection = linux_read_u32_order(req, 8, little);
                 let target = linux_read_u32_order(req, 12, little);
                 let property = linux_read_u32_order(req, 16, little);
+                let owner = linux_x11_get_selection_owner(state, selection);
+                if owner != 0 {
+                    // A Linux window owns it and answers, as on any X server.
+                    linux_x11_queue_selection_request(state, sock_idx, owner, requestor, selection, target, property);
+                    return;
+                }
+                // Nobody on the Linux side owns it: answer from the desktop
+                // clipboard, limited to one property slot.
+                let mut answered = property;
+                let text = crate::gui::clipboard::get().map(|payload| payload.as_text());
                 if property != 0 {
                     if target == LINUX_X11_ATOM_TARGETS {
                         let mut targets = [0u8; 12];
@@ -8328,21 +8797,14 @@ fn linux_x11_handle_request(state: &mut LinuxShimState, sock_idx: usize, opcode:
                             0,
                             &targets,
                         );
-                    } else if target == LINUX_X11_ATOM_UTF8_STRING || target == LINUX_X11_ATOM_STRING {
-                        linux_x11_set_property(
-                            state,
-                            requestor,
-                            property,
-                            target,
-                            8,
-                            0,
-                            b"Go OS Linux bridge clipboard",
-                        );
+                    } else if let Some(text) = text
+                        .as_ref()
+                        .filter(|_| target == LINUX_X11_ATOM_UTF8_STRING || target == LINUX_X11_ATOM_STRING)
+                    {
+                        linux_x11_set_property(state, requestor, property, target, 8, 0, text.as_bytes());
                     } else {
-                        let owner = linux_x11_get_selection_owner(state, selection);
-                        if owner == 0 {
-                            linux_x11_remove_property(state, requestor, property);
-                        }
+                        linux_x11_remove_property(state, requestor, property);
+                        answered = 0;
                     }
                 }
                 let mut ev = [0u8; 28];
@@ -8350,7 +8812,7 @@ fn linux_x11_handle_request(state: &mut LinuxShimState, sock_idx: usize, opcode:
                 linux_write_u32_order(&mut ev, 4, requestor, little);
                 linux_write_u32_order(&mut ev, 8, selection, little);
                 linux_write_u32_order(&mut ev, 12, target, little);
-                linux_write_u32_order(&mut ev, 16, property, little);
+                linux_write_u32_order(&mut ev, 16, answered, little);
                 linux_x11_queue_window_event(
                     state,
                     sock_idx,
@@ -9615,7 +10077,9 @@ fn linux_parse_sockaddr_un_path(addr_ptr: u64, addr_len: u64, out: &mut [u8; LIN
     if addr_len > core::mem::size_of::<LinuxSockAddrUn>() as u64 {
         return Err(linux_neg_errno(22));
     }
-    let addr = unsafe { ptr::read(addr_ptr as *const LinuxSockAddrUn) };
+    let Ok(addr) = crate::uaccess::read_user::<LinuxSockAddrUn>(addr_ptr) else {
+        return Err(linux_neg_errno(14)); // EFAULT
+    };
     if addr.family != LINUX_AF_UNIX {
         return Err(linux_neg_errno(97)); // EAFNOSUPPORT
     }
@@ -9785,6 +10249,13 @@ fn linux_close_open_slot(state: &mut LinuxShimState, open_idx: usize) {
                 }
             }
         }
+        LINUX_OPEN_KIND_VFS => {
+            // Duplicated descriptors share the handle; the last one stores and releases it.
+            state.open_files[open_idx].active = false;
+            if !linux_is_open_kind_present(state, LINUX_OPEN_KIND_VFS, slot.object_index) {
+                let _ = crate::fs::vfs::Handle::from_id(slot.object_index as u64).close();
+            }
+        }
         _ => {}
     }
     state.open_files[open_idx] = LinuxOpenFileSlot::empty();
@@ -9881,6 +10352,7 @@ fn linux_add_process_slot(
     brk_base: u64,
     brk_current: u64,
     brk_limit: u64,
+    mmap_base: u64,
     mmap_cursor: u64,
     mmap_count: usize,
 ) -> Option<usize> {
@@ -9896,6 +10368,7 @@ fn linux_add_process_slot(
                 brk_base,
                 brk_current,
                 brk_limit,
+                mmap_base,
                 mmap_cursor,
                 mmap_count,
             };
@@ -9971,6 +10444,7 @@ fn linux_sync_current_process_to_slot(state: &mut LinuxShimState) {
     slot.brk_base = state.brk_base;
     slot.brk_current = state.brk_current;
     slot.brk_limit = state.brk_limit;
+    slot.mmap_base = state.mmap_base;
     slot.mmap_cursor = state.mmap_cursor;
     slot.mmap_count = state.mmap_count;
 }
@@ -9983,6 +10457,7 @@ fn linux_sync_current_process_from_slot(state: &mut LinuxShimState) {
     state.brk_base = slot.brk_base;
     state.brk_current = slot.brk_current;
     state.brk_limit = slot.brk_limit;
+    state.mmap_base = slot.mmap_base;
     state.mmap_cursor = slot.mmap_cursor;
     state.mmap_count = slot.mmap_count;
 }
@@ -10534,7 +11009,9 @@ fn linux_futex_timeout_deadline_from_ptr(timeout_ptr: u64, absolute: bool) -> Re
     if timeout_ptr == 0 {
         return Ok(None);
     }
-    let ts = unsafe { ptr::read(timeout_ptr as *const LinuxTimespec) };
+    let Ok(ts) = crate::uaccess::read_user::<LinuxTimespec>(timeout_ptr) else {
+        return Err(linux_neg_errno(14)); // EFAULT
+    };
     if ts.tv_sec < 0 || ts.tv_nsec < 0 || ts.tv_nsec >= 1_000_000_000 {
         return Err(linux_neg_errno(22)); // EINVAL
     }
@@ -10710,22 +11187,24 @@ fn linux_futex_pi_lock(state: &mut LinuxShimState, uaddr: u64, try_only: bool) -
     if self_tid == 0 {
         return linux_neg_errno(22); // EINVAL
     }
-    let owner_word = unsafe { ptr::read_volatile(uaddr as *const u32) };
+    let Ok(owner_word) = crate::uaccess::read_user::<u32>(uaddr) else {
+        return linux_neg_errno(14); // EFAULT
+    };
     let owner_tid = owner_word & LINUX_FUTEX_TID_MASK;
     if owner_tid == 0 {
         let mut new_word = owner_word & !LINUX_FUTEX_TID_MASK;
         new_word &= !LINUX_FUTEX_OWNER_DIED;
         new_word |= self_tid & LINUX_FUTEX_TID_MASK;
-        unsafe {
-            ptr::write_volatile(uaddr as *mut u32, new_word);
+        if crate::uaccess::write_user(uaddr, new_word).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
         return 0;
     }
     if owner_tid == self_tid {
         return linux_neg_errno(35); // EDEADLK
     }
-    unsafe {
-        ptr::write_volatile(uaddr as *mut u32, owner_word | LINUX_FUTEX_WAITERS);
+    if crate::uaccess::write_user(uaddr, owner_word | LINUX_FUTEX_WAITERS).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
     if try_only {
         return linux_neg_errno(11); // EAGAIN
@@ -10745,7 +11224,9 @@ fn linux_futex_pi_unlock(state: &mut LinuxShimState, uaddr: u64) -> i64 {
     if self_tid == 0 {
         return linux_neg_errno(22); // EINVAL
     }
-    let owner_word = unsafe { ptr::read_volatile(uaddr as *const u32) };
+    let Ok(owner_word) = crate::uaccess::read_user::<u32>(uaddr) else {
+        return linux_neg_errno(14); // EFAULT
+    };
     let owner_tid = owner_word & LINUX_FUTEX_TID_MASK;
     if owner_tid != 0 && owner_tid != self_tid {
         return linux_neg_errno(1); // EPERM
@@ -10762,8 +11243,8 @@ fn linux_futex_pi_unlock(state: &mut LinuxShimState, uaddr: u64) -> i64 {
     } else {
         new_word &= !LINUX_FUTEX_WAITERS;
     }
-    unsafe {
-        ptr::write_volatile(uaddr as *mut u32, new_word);
+    if crate::uaccess::write_user(uaddr, new_word).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
     let _ = linux_wake_futex_waiters(state, uaddr, 1);
     0
@@ -10777,7 +11258,9 @@ fn linux_futex_wake_op_eval_and_store(uaddr2: u64, encoded: u32) -> Result<bool,
         return Err(linux_neg_errno(22)); // EINVAL
     }
 
-    let old = unsafe { ptr::read_volatile(uaddr2 as *const u32) };
+    let Ok(old) = crate::uaccess::read_user::<u32>(uaddr2) else {
+        return Err(linux_neg_errno(14)); // EFAULT
+    };
     let op = (encoded >> 28) & 0x0f;
     let cmp = (encoded >> 24) & 0x0f;
     let mut oparg = (encoded >> 12) & 0x0fff;
@@ -10796,8 +11279,8 @@ fn linux_futex_wake_op_eval_and_store(uaddr2: u64, encoded: u32) -> Result<bool,
         LINUX_FUTEX_OP_XOR => old ^ oparg,
         _ => return Err(linux_neg_errno(22)), // EINVAL
     };
-    unsafe {
-        ptr::write_volatile(uaddr2 as *mut u32, new);
+    if crate::uaccess::write_user(uaddr2, new).is_err() {
+        return Err(linux_neg_errno(14)); // EFAULT
     }
 
     let old_i = old as i32;
@@ -10889,7 +11372,9 @@ fn linux_requeue_pi_waiters(
     }
 
     if moved > 0 {
-        let owner_word = unsafe { ptr::read_volatile(uaddr2 as *const u32) };
+        let Ok(owner_word) = crate::uaccess::read_user::<u32>(uaddr2) else {
+            return linux_neg_errno(14); // EFAULT
+        };
         let owner_tid = owner_word & LINUX_FUTEX_TID_MASK;
         let mut new_word = owner_word | LINUX_FUTEX_WAITERS;
         let mut promoted_tid = 0u32;
@@ -10898,15 +11383,14 @@ fn linux_requeue_pi_waiters(
             new_word &= !(LINUX_FUTEX_TID_MASK | LINUX_FUTEX_OWNER_DIED);
             new_word |= promoted_tid & LINUX_FUTEX_TID_MASK;
         }
-        unsafe {
-            ptr::write_volatile(uaddr2 as *mut u32, new_word);
+        if crate::uaccess::write_user(uaddr2, new_word).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
         if promoted_tid != 0 {
             let _ = linux_wake_specific_futex_waiter(state, promoted_tid, uaddr2, 0);
             if linux_count_futex_waiters(state, uaddr2) == 0 {
-                let cur = unsafe { ptr::read_volatile(uaddr2 as *const u32) };
-                unsafe {
-                    ptr::write_volatile(uaddr2 as *mut u32, cur & !LINUX_FUTEX_WAITERS);
+                if let Ok(cur) = crate::uaccess::read_user::<u32>(uaddr2) {
+                    let _ = crate::uaccess::write_user(uaddr2, cur & !LINUX_FUTEX_WAITERS);
                 }
             }
         }
@@ -11141,7 +11625,23 @@ fn linux_release_all_runtime_blobs(state: &mut LinuxShimState) {
     state.runtime_blob_bytes = 0;
 }
 
+/// W^X for a guest mapping: PROT_EXEC pages become read-only and executable,
+/// all others stay writable and non-executable. PROT_WRITE alone is not
+/// enforced.
+fn linux_apply_mmap_prot(slot: &mut LinuxMmapSlot, addr: u64, len: u64, prot: u64) {
+    let executable = (prot & LINUX_PROT_EXEC) != 0;
+    if !executable && !slot.exec_pages {
+        return;
+    }
+    if crate::wx::set_guest_exec(addr, len, executable) {
+        slot.exec_pages = true;
+    }
+}
+
 fn linux_release_mmap_slot(slot: &mut LinuxMmapSlot) {
+    if slot.exec_pages {
+        crate::wx::set_guest_exec(slot.backing_ptr, slot.backing_len, false);
+    }
     if slot.backing_ptr != 0 && slot.backing_len > 0 && slot.backing_len <= usize::MAX as u64 {
         if let Ok(layout) = Layout::from_size_align(slot.backing_len as usize, LINUX_PAGE_SIZE as usize) {
             unsafe {
@@ -11161,12 +11661,12 @@ fn linux_release_all_mmaps(state: &mut LinuxShimState) {
         i += 1;
     }
     state.mmap_count = 0;
-    state.mmap_cursor = LINUX_MMAP_BASE;
+    state.mmap_cursor = state.mmap_base;
     let mut p = 0usize;
     while p < LINUX_MAX_PROCESSES {
         if state.processes[p].active {
             state.processes[p].mmap_count = 0;
-            state.processes[p].mmap_cursor = LINUX_MMAP_BASE;
+            state.processes[p].mmap_cursor = state.processes[p].mmap_base;
         }
         p += 1;
     }
@@ -11185,11 +11685,11 @@ fn linux_release_process_mmaps(state: &mut LinuxShimState, pid: u32) {
     }
     if let Some(proc_idx) = linux_find_process_slot_index(state, pid) {
         state.processes[proc_idx].mmap_count = 0;
-        state.processes[proc_idx].mmap_cursor = LINUX_MMAP_BASE;
+        state.processes[proc_idx].mmap_cursor = state.processes[proc_idx].mmap_base;
     }
     if state.current_pid == pid {
         state.mmap_count = 0;
-        state.mmap_cursor = LINUX_MMAP_BASE;
+        state.mmap_cursor = state.mmap_base;
     }
 }
 
@@ -11210,33 +11710,30 @@ fn linux_write_stat64_mode(stat_ptr: u64, size: u64, mode: u32) -> i64 {
     }
     let blocks = (size.saturating_add(511)) / 512;
     let now = (timer::ticks() / 1000) as i64;
-    unsafe {
-        let out = stat_ptr as *mut LinuxStat64;
-        ptr::write(
-            out,
-            LinuxStat64 {
-                st_dev: 1,
-                st_ino: 1,
-                st_nlink: 1,
-                st_mode: mode,
-                st_uid: 0,
-                st_gid: 0,
-                __pad0: 0,
-                st_rdev: 0,
-                st_size: size as i64,
-                st_blksize: 4096,
-                st_blocks: blocks as i64,
-                st_atime: now,
-                st_atime_nsec: 0,
-                st_mtime: now,
-                st_mtime_nsec: 0,
-                st_ctime: now,
-                st_ctime_nsec: 0,
-                __unused: [0; 3],
-            },
-        );
+    let stat = LinuxStat64 {
+        st_dev: 1,
+        st_ino: 1,
+        st_nlink: 1,
+        st_mode: mode,
+        st_uid: 0,
+        st_gid: 0,
+        __pad0: 0,
+        st_rdev: 0,
+        st_size: size as i64,
+        st_blksize: 4096,
+        st_blocks: blocks as i64,
+        st_atime: now,
+        st_atime_nsec: 0,
+        st_mtime: now,
+        st_mtime_nsec: 0,
+        st_ctime: now,
+        st_ctime_nsec: 0,
+        __unused: [0; 3],
+    };
+    match crate::uaccess::write_user(stat_ptr, stat) {
+        Ok(()) => 0,
+        Err(_) => linux_neg_errno(14), // EFAULT
     }
-    0
 }
 
 fn linux_write_stat64(stat_ptr: u64, size: u64) -> i64 {
@@ -11514,10 +12011,9 @@ fn linux_parse_x11_display_from_inet(addr_ptr: u64, addr_len: u64) -> Option<u16
     if addr_ptr == 0 || addr_len < 4 {
         return None;
     }
-    let family = unsafe { ptr::read(addr_ptr as *const u16) };
-    let port_hi = unsafe { ptr::read(addr_ptr.saturating_add(2) as *const u8) } as u16;
-    let port_lo = unsafe { ptr::read(addr_ptr.saturating_add(3) as *const u8) } as u16;
-    let port = (port_hi << 8) | port_lo;
+    let head = crate::uaccess::read_user::<[u8; 4]>(addr_ptr).ok()?;
+    let family = u16::from_le_bytes([head[0], head[1]]);
+    let port = u16::from_be_bytes([head[2], head[3]]);
     if !(LINUX_X11_TCP_PORT_BASE..=LINUX_X11_TCP_PORT_MAX).contains(&port) {
         return None;
     }
@@ -11612,7 +12108,9 @@ fn linux_stdio_push_line(state: &mut LinuxShimState) {
     if state.stdio_line_len == 0 {
         return;
     }
-    ui::terminal_system_message_bytes(&state.stdio_line[..state.stdio_line_len]);
+    if !crate::procenv::write_line(state.stdio_fd, &state.stdio_line[..state.stdio_line_len]) {
+        ui::terminal_system_message_bytes(&state.stdio_line[..state.stdio_line_len]);
+    }
     unsafe {
         if LINUX_GFX_BRIDGE.active {
             let gfx = &mut LINUX_GFX_BRIDGE;
@@ -11672,13 +12170,17 @@ fn linux_stdio_capture_from_ptr(state: &mut LinuxShimState, ptr_raw: u64, len: u
     } else {
         (len as usize).min(max_capture)
     };
-    unsafe {
-        let src = ptr_raw as *const u8;
-        let mut i = 0usize;
-        while i < capture {
-            linux_stdio_push_byte(state, ptr::read(src.add(i)));
-            i += 1;
+    let mut chunk = [0u8; 256];
+    let mut done = 0usize;
+    while done < capture {
+        let n = (capture - done).min(chunk.len());
+        if crate::uaccess::copy_from_user(&mut chunk[..n], ptr_raw + done as u64).is_err() {
+            return linux_neg_errno(14); // EFAULT
+        }
+        for &b in &chunk[..n] {
+            linux_stdio_push_byte(state, b);
         }
+        done += n;
     }
     ret_len
 }
@@ -11719,8 +12221,9 @@ fn linux_sys_write(state: &mut LinuxShimState, fd: u64, buf: u64, len: u64) -> i
                 }
                 if write_len > 0 {
                     let dst_ptr = state.runtime_files[runtime_idx].data_ptr.saturating_add(cursor);
-                    unsafe {
-                        ptr::copy_nonoverlapping(buf as *const u8, dst_ptr as *mut u8, write_len as usize);
+                    let dst = unsafe { core::slice::from_raw_parts_mut(dst_ptr as *mut u8, write_len as usize) };
+                    if crate::uaccess::copy_from_user(dst, buf).is_err() {
+                        return linux_neg_errno(14); // EFAULT
                     }
                 }
                 state.open_files[open_idx].cursor = end;
@@ -11742,14 +12245,14 @@ fn linux_sys_write(state: &mut LinuxShimState, fd: u64, buf: u64, len: u64) -> i
                 
                 let mut write_buf = crate::alloc::vec::Vec::with_capacity(to_write);
                 write_buf.resize(to_write, 0);
-                unsafe {
-                    ptr::copy_nonoverlapping(buf as *const u8, write_buf.as_mut_ptr(), to_write);
+                if crate::uaccess::copy_from_user(&mut write_buf, buf).is_err() {
+                    return linux_neg_errno(14); // EFAULT
                 }
                 
                 // Currently, fat32 has write_text_file_in_dir but not a direct write_file_range.
                 // We will add write_file_range next, but for now we will just assume it exists.
                 unsafe {
-                    let fat = &mut crate::fat32::GLOBAL_FAT;
+                    let fat = crate::fat32::GLOBAL_FAT.get_mut();
                     let written_len = fat.write_file_range(cluster, cursor as usize, &write_buf).unwrap_or(0);
                     if written_len > 0 {
                         state.open_files[open_idx].cursor = cursor.saturating_add(written_len as u64);
@@ -11757,6 +12260,26 @@ fn linux_sys_write(state: &mut LinuxShimState, fd: u64, buf: u64, len: u64) -> i
                     return written_len as i64;
                 }
             }
+            LINUX_OPEN_KIND_VFS => {
+                let mut write_buf = alloc::vec![0u8; len.min(LINUX_VFS_IO_CHUNK) as usize];
+                if crate::uaccess::copy_from_user(&mut write_buf, buf).is_err() {
+                    return linux_neg_errno(14); // EFAULT
+                }
+                let handle = crate::fs::vfs::Handle::from_id(slot.object_index as u64);
+                let written_len = match handle.write_at(slot.cursor, &write_buf) {
+                    Ok(len) => len,
+                    // EFBIG and ENOSPC, else EBADF: not open for writing.
+                    Err(crate::fs::vfs::TOO_BIG) => return linux_neg_errno(27),
+                    Err(crate::fs::vfs::NO_SPACE) => return linux_neg_errno(28),
+                    Err(_) => return linux_neg_errno(9),
+                };
+                state.open_files[open_idx].cursor = if (slot.flags & LINUX_O_APPEND) != 0 {
+                    handle.stat().map(|stat| stat.size).unwrap_or(slot.cursor)
+                } else {
+                    slot.cursor.saturating_add(written_len as u64)
+                };
+                return written_len as i64;
+            }
             LINUX_OPEN_KIND_STDIO_DUP => {
                 let target = slot.aux as i32;
                 if target == 1 || target == 2 {
@@ -11772,7 +12295,9 @@ fn linux_sys_write(state: &mut LinuxShimState, fd: u64, buf: u64, len: u64) -> i
                 if slot.object_index >= LINUX_MAX_EVENTFDS || !state.eventfds[slot.object_index].active {
                     return linux_neg_errno(9);
                 }
-                let value = unsafe { ptr::read(buf as *const u64) };
+                let Ok(value) = crate::uaccess::read_user::<u64>(buf) else {
+                    return linux_neg_errno(14); // EFAULT
+                };
                 if value == u64::MAX {
                     return linux_neg_errno(22); // EINVAL
                 }
@@ -11810,7 +12335,11 @@ fn linux_sys_write(state: &mut LinuxShimState, fd: u64, buf: u64, len: u64) -> i
         return linux_neg_errno(9);
     }
 
-    if stdio_target.is_some() {
+    if let Some(target) = stdio_target {
+        if state.stdio_fd != target as u32 {
+            linux_stdio_push_line(state);
+            state.stdio_fd = target as u32;
+        }
         state.write_calls = state.write_calls.saturating_add(1);
         let result = linux_stdio_capture_from_ptr(state, buf, len, LINUX_STDIO_CAPTURE_LIMIT);
         if result >= 0 {
@@ -11831,29 +12360,32 @@ fn linux_sys_writev(state: &mut LinuxShimState, fd: u64, iov_ptr: u64, iov_cnt:
     }
 
     let mut total_written = 0u64;
-    unsafe {
-        let base = iov_ptr as *const LinuxIovec;
-        let mut i = 0usize;
-        while i < count {
-            let iov = ptr::read(base.add(i));
-            if iov.len == 0 {
-                i += 1;
-                continue;
-            }
-            let res = linux_sys_write(state, fd, iov.base, iov.len);
-            if res < 0 {
-                if total_written > 0 {
-                    return total_written.min(i64::MAX as u64) as i64;
-                }
-                return res;
-            }
-            let wrote = res as u64;
-            total_written = total_written.saturating_add(wrote);
-            if wrote < iov.len {
+    let mut i = 0usize;
+    while i < count {
+        let entry = iov_ptr + (i * core::mem::size_of::<LinuxIovec>()) as u64;
+        let Ok(iov) = crate::uaccess::read_user::<LinuxIovec>(entry) else {
+            if total_written > 0 {
                 break;
             }
+            return linux_neg_errno(14); // EFAULT
+        };
+        if iov.len == 0 {
             i += 1;
+            continue;
+        }
+        let res = linux_sys_write(state, fd, iov.base, iov.len);
+        if res < 0 {
+            if total_written > 0 {
+                return total_written.min(i64::MAX as u64) as i64;
+            }
+            return res;
         }
+        let wrote = res as u64;
+        total_written = total_written.saturating_add(wrote);
+        if wrote < iov.len {
+            break;
+        }
+        i += 1;
     }
     total_written.min(i64::MAX as u64) as i64
 }
@@ -11868,29 +12400,32 @@ fn linux_sys_readv(state: &mut LinuxShimState, fd: u64, iov_ptr: u64, iov_cnt: u
     }
 
     let mut total_read = 0u64;
-    unsafe {
-        let base = iov_ptr as *const LinuxIovec;
-        let mut i = 0usize;
-        while i < count {
-            let iov = ptr::read(base.add(i));
-            if iov.len == 0 {
-                i += 1;
-                continue;
-            }
-            let res = linux_sys_read(state, fd, iov.base, iov.len);
-            if res < 0 {
-                if total_read > 0 {
-                    return total_read.min(i64::MAX as u64) as i64;
-                }
-                return res;
-            }
-            let got = res as u64;
-            total_read = total_read.saturating_add(got);
-            if got < iov.len {
+    let mut i = 0usize;
+    while i < count {
+        let entry = iov_ptr + (i * core::mem::size_of::<LinuxIovec>()) as u64;
+        let Ok(iov) = crate::uaccess::read_user::<LinuxIovec>(entry) else {
+            if total_read > 0 {
                 break;
             }
+            return linux_neg_errno(14); // EFAULT
+        };
+        if iov.len == 0 {
             i += 1;
+            continue;
+        }
+        let res = linux_sys_read(state, fd, iov.base, iov.len);
+        if res < 0 {
+            if total_read > 0 {
+                return total_read.min(i64::MAX as u64) as i64;
+            }
+            return res;
+        }
+        let got = res as u64;
+        total_read = total_read.saturating_add(got);
+        if got < iov.len {
+            break;
         }
+        i += 1;
     }
     total_read.min(i64::MAX as u64) as i64
 }
@@ -11927,12 +12462,11 @@ fn linux_sys_pread64(state: &mut LinuxShimState, fd: u64, buf: u64, len: u64, of
     }
     let remaining = readable_len.saturating_sub(offset);
     let to_copy = remaining.min(len).min(i64::MAX as u64);
-    unsafe {
-        ptr::copy_nonoverlapping(
-            runtime.data_ptr.saturating_add(offset) as *const u8,
-            buf as *mut u8,
-            to_copy as usize,
-        );
+    let src = unsafe {
+        core::slice::from_raw_parts(runtime.data_ptr.saturating_add(offset) as *const u8, to_copy as usize)
+    };
+    if crate::uaccess::copy_to_user(buf, src).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
     to_copy as i64
 }
@@ -11955,8 +12489,8 @@ fn linux_sys_ioctl(state: &mut LinuxShimState, fd: u64, req: u64, argp: u64) ->
         if argp == 0 {
             return linux_neg_errno(14); // EFAULT
         }
-        unsafe {
-            ptr::write(argp as *mut LinuxTermios, LinuxTermios::empty());
+        if crate::uaccess::write_user(argp, LinuxTermios::empty()).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
         return 0;
     }
@@ -11975,8 +12509,8 @@ fn linux_sys_ioctl(state: &mut LinuxShimState, fd: u64, req: u64, argp: u64) ->
         } else {
             1
         };
-        unsafe {
-            ptr::write(argp as *mut i32, pgrp);
+        if crate::uaccess::write_user(argp, pgrp).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
         return 0;
     }
@@ -11990,16 +12524,14 @@ fn linux_sys_ioctl(state: &mut LinuxShimState, fd: u64, req: u64, argp: u64) ->
         if argp == 0 {
             return linux_neg_errno(14); // EFAULT
         }
-        unsafe {
-            ptr::write(
-                argp as *mut LinuxWinsize,
-                LinuxWinsize {
-                    ws_row: 24,
-                    ws_col: ui::TERM_MAX_INPUT as u16,
-                    ws_xpixel: 0,
-                    ws_ypixel: 0,
-                },
-            );
+        let winsize = LinuxWinsize {
+            ws_row: 24,
+            ws_col: ui::TERM_MAX_INPUT as u16,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        if crate::uaccess::write_user(argp, winsize).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
         return 0;
     }
@@ -12007,7 +12539,10 @@ fn linux_sys_ioctl(state: &mut LinuxShimState, fd: u64, req: u64, argp: u64) ->
         if argp == 0 {
             return linux_neg_errno(14); // EFAULT
         }
-        let enabled = unsafe { ptr::read(argp as *const i32) } != 0;
+        let Ok(flag) = crate::uaccess::read_user::<i32>(argp) else {
+            return linux_neg_errno(14); // EFAULT
+        };
+        let enabled = flag != 0;
         if let Some(open_idx) = open_idx {
             if enabled {
                 state.open_files[open_idx].flags |= LINUX_O_NONBLOCK;
@@ -12067,8 +12602,8 @@ fn linux_sys_ioctl(state: &mut LinuxShimState, fd: u64, req: u64, argp: u64) ->
                 _ => {}
             }
         }
-        unsafe {
-            ptr::write(argp as *mut i32, available.min(i32::MAX as u64) as i32);
+        if crate::uaccess::write_user(argp, available.min(i32::MAX as u64) as i32).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
         return 0;
     }
@@ -12131,15 +12666,62 @@ fn linux_sys_getcwd(buf: u64, size: u64) -> i64 {
     if buf == 0 {
         return linux_neg_errno(14); // EFAULT
     }
-    if size < 2 {
+    let mut cwd = [0u8; LINUX_PATH_MAX];
+    let len = crate::procenv::with_cwd(|path| {
+        let n = path.len().min(LINUX_PATH_MAX - 1);
+        cwd[..n].copy_from_slice(&path.as_bytes()[..n]);
+        n
+    });
+    if size < (len + 1) as u64 {
         return linux_neg_errno(34); // ERANGE
     }
-    unsafe {
-        let dst = buf as *mut u8;
-        ptr::write(dst, b'/');
-        ptr::write(dst.add(1), 0);
+    if crate::uaccess::copy_to_user(buf, &cwd[..len + 1]).is_err() {
+        return linux_neg_errno(14); // EFAULT
+    }
+    (len + 1) as i64
+}
+
+fn linux_sys_chdir(state: &mut LinuxShimState, path_ptr: u64) -> i64 {
+    let mut input = [0u8; LINUX_PATH_MAX];
+    let input_len = match linux_read_c_string(path_ptr, &mut input) {
+        Ok(v) => v,
+        Err(err) => return err,
+    };
+    let mut normalized = [0u8; LINUX_PATH_MAX];
+    let path_len = match linux_resolve_open_path(state, LINUX_AT_FDCWD, &input, input_len, &mut normalized) {
+        Ok(v) => v,
+        Err(err) => return err,
+    };
+    let (exists, is_file, _, _, _) = linux_vfs_lookup_path(state, &normalized, path_len);
+    if !exists {
+        return linux_neg_errno(2); // ENOENT
+    }
+    if is_file {
+        return linux_neg_errno(20); // ENOTDIR
+    }
+    // Paths come back lowercased and rooted at /linuxrt; the cwd is kept
+    // as the guest sees it.
+    let guest = normalized[..path_len].strip_prefix(b"/linuxrt").unwrap_or(&normalized[..path_len]);
+    match core::str::from_utf8(guest) {
+        Ok(path) if crate::procenv::set_cwd(path) => 0,
+        _ => linux_neg_errno(2), // ENOENT
+    }
+}
+
+/// Read from fd 0: lines typed in the terminal the program is bound to.
+fn linux_sys_read_stdin(buf: u64, len: u64) -> i64 {
+    let mut chunk = [0u8; 256];
+    let want = (len as usize).min(chunk.len());
+    match crate::procenv::read_stdin(&mut chunk[..want]) {
+        None => 0,
+        Some(0) => linux_neg_errno(11), // EAGAIN: nothing typed yet
+        Some(n) => {
+            if crate::uaccess::copy_to_user(buf, &chunk[..n]).is_err() {
+                return linux_neg_errno(14); // EFAULT
+            }
+            n as i64
+        }
     }
-    2
 }
 
 fn linux_sys_readlink(state: &LinuxShimState, path_ptr: u64, buf: u64, buf_size: u64) -> i64 {
@@ -12181,8 +12763,8 @@ fn linux_sys_readlink(state: &LinuxShimState, path_ptr: u64, buf: u64, buf_size:
     };
 
     let copy_len = target_len.min(buf_size as usize);
-    unsafe {
-        ptr::copy_nonoverlapping(target.as_ptr(), buf as *mut u8, copy_len);
+    if crate::uaccess::copy_to_user(buf, &target[..copy_len]).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
     copy_len as i64
 }
@@ -12226,8 +12808,8 @@ fn linux_sys_readlinkat(state: &LinuxShimState, dirfd: u64, path_ptr: u64, buf:
     };
 
     let copy_len = target_len.min(buf_size as usize);
-    unsafe {
-        ptr::copy_nonoverlapping(target.as_ptr(), buf as *mut u8, copy_len);
+    if crate::uaccess::copy_to_user(buf, &target[..copy_len]).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
     copy_len as i64
 }
@@ -12405,6 +12987,11 @@ fn linux_sys_getdents64(state: &mut LinuxShimState, fd: u64, dirp: u64, count: u
         push_entry("tmp", LINUX_DT_DIR);
         push_entry("run", LINUX_DT_DIR);
         push_entry("var", LINUX_DT_DIR);
+        for mount in crate::fs::vfs::volume_mounts().iter() {
+            if let Some(name) = mount.path.strip_prefix('/').filter(|name| !name.contains('/')) {
+                push_entry(name, LINUX_DT_DIR);
+            }
+        }
     } else if linux_path_equals(&dir_path, dir_path_len, "/proc") {
         push_entry("self", LINUX_DT_DIR);
     } else if linux_path_equals(&dir_path, dir_path_len, "/proc/self") {
@@ -12479,10 +13066,15 @@ fn linux_sys_getdents64(state: &mut LinuxShimState, fd: u64, dirp: u64, count: u
         i += 1;
     }
 
-    if let Some(fs_meta) = linux_fat_lookup_guest_path(&dir_path, dir_path_len) {
+    if let Some(mounted) = linux_mounted_path(&dir_path, dir_path_len) {
+        for entry in crate::fs::vfs::read_dir(mounted).unwrap_or_default().iter() {
+            let d_type = if entry.is_dir { LINUX_DT_DIR } else { LINUX_DT_REG };
+            push_entry(entry.name.as_str(), d_type);
+        }
+    } else if let Some(fs_meta) = linux_fat_lookup_guest_path(&dir_path, dir_path_len) {
         if fs_meta.exists && !fs_meta.is_file && fs_meta.cluster >= 2 {
             unsafe {
-                let fat = &mut crate::fat32::GLOBAL_FAT;
+                let fat = crate::fat32::GLOBAL_FAT.get_mut();
                 if fat.init_status == crate::fat32::InitStatus::Success {
                     if let Ok(fs_entries) = fat.read_dir_entries(fs_meta.cluster) {
                         for entry in fs_entries.iter() {
@@ -12627,7 +13219,7 @@ fn linux_poll_ready_mask(state: &LinuxShimState, fd: i32, events: i16) -> i16 {
 
     let mut ready = 0i16;
     match slot.kind {
-        LINUX_OPEN_KIND_RUNTIME => {
+        LINUX_OPEN_KIND_RUNTIME | LINUX_OPEN_KIND_VFS => {
             if (events & LINUX_POLLIN) != 0 {
                 ready |= LINUX_POLLIN;
             }
@@ -12744,19 +13336,21 @@ fn linux_sys_poll(state: &LinuxShimState, fds_ptr: u64, nfds: u64, _timeout_ms:
     }
     let count = (nfds as usize).min(1024);
     let mut ready_count = 0i64;
-    unsafe {
-        let fds = fds_ptr as *mut LinuxPollFd;
-        let mut i = 0usize;
-        while i < count {
-            let mut slot = ptr::read(fds.add(i));
-            let ready = linux_poll_ready_mask(state, slot.fd, slot.events);
-            slot.revents = ready;
-            if ready != 0 {
-                ready_count += 1;
-            }
-            ptr::write(fds.add(i), slot);
-            i += 1;
+    let mut i = 0usize;
+    while i < count {
+        let entry = fds_ptr + (i * core::mem::size_of::<LinuxPollFd>()) as u64;
+        let Ok(mut slot) = crate::uaccess::read_user::<LinuxPollFd>(entry) else {
+            return linux_neg_errno(14); // EFAULT
+        };
+        let ready = linux_poll_ready_mask(state, slot.fd, slot.events);
+        slot.revents = ready;
+        if ready != 0 {
+            ready_count += 1;
+        }
+        if crate::uaccess::write_user(entry, slot).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
+        i += 1;
     }
     ready_count
 }
@@ -12810,12 +13404,11 @@ fn linux_socket_send_payload(state: &mut LinuxShimState, sock_idx: usize, buf: u
         let mut remaining = len.min(i64::MAX as u64);
         while remaining > 0 {
             let copy_len = remaining.min(chunk.len() as u64) as usize;
-            unsafe {
-                ptr::copy_nonoverlapping(
-                    buf.saturating_add(sent) as *const u8,
-                    chunk.as_mut_ptr(),
-                    copy_len,
-                );
+            if crate::uaccess::copy_from_user(&mut chunk[..copy_len], buf.saturating_add(sent)).is_err() {
+                if sent == 0 {
+                    return linux_neg_errno(14); // EFAULT
+                }
+                break;
             }
             linux_dbus_consume_payload(&mut state.sockets[sock_idx], &chunk[..copy_len]);
             sent = sent.saturating_add(copy_len as u64);
@@ -12830,12 +13423,11 @@ fn linux_socket_send_payload(state: &mut LinuxShimState, sock_idx: usize, buf: u
         let mut remaining = len.min(i64::MAX as u64);
         while remaining > 0 {
             let copy_len = remaining.min(chunk.len() as u64) as usize;
-            unsafe {
-                ptr::copy_nonoverlapping(
-                    buf.saturating_add(sent) as *const u8,
-                    chunk.as_mut_ptr(),
-                    copy_len,
-                );
+            if crate::uaccess::copy_from_user(&mut chunk[..copy_len], buf.saturating_add(sent)).is_err() {
+                if sent == 0 {
+                    return linux_neg_errno(14); // EFAULT
+                }
+                break;
             }
             linux_x11_consume_payload(state, sock_idx, &chunk[..copy_len]);
             sent = sent.saturating_add(copy_len as u64);
@@ -12850,12 +13442,11 @@ fn linux_socket_send_payload(state: &mut LinuxShimState, sock_idx: usize, buf: u
         let mut remaining = len.min(i64::MAX as u64);
         while remaining > 0 {
             let copy_len = remaining.min(chunk.len() as u64) as usize;
-            unsafe {
-                ptr::copy_nonoverlapping(
-                    buf.saturating_add(sent) as *const u8,
-                    chunk.as_mut_ptr(),
-                    copy_len,
-                );
+            if crate::uaccess::copy_from_user(&mut chunk[..copy_len], buf.saturating_add(sent)).is_err() {
+                if sent == 0 {
+                    return linux_neg_errno(14); // EFAULT
+                }
+                break;
             }
             linux_wayland_consume_payload(state, sock_idx, &chunk[..copy_len]);
             sent = sent.saturating_add(copy_len as u64);
@@ -12882,15 +13473,10 @@ fn linux_socket_send_payload(state: &mut LinuxShimState, sock_idx: usize, buf: u
             return linux_neg_errno(11); // EAGAIN
         }
         let write_len = free.min(len.min(i64::MAX as u64) as usize);
-        unsafe {
-            ptr::copy_nonoverlapping(
-                buf as *const u8,
-                state.sockets[peer_idx]
-                    .rx_buf
-                    .as_mut_ptr()
-                    .add(state.sockets[peer_idx].rx_len),
-                write_len,
-            );
+        let rx_len = state.sockets[peer_idx].rx_len;
+        let dst = &mut state.sockets[peer_idx].rx_buf[rx_len..rx_len + write_len];
+        if crate::uaccess::copy_from_user(dst, buf).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
         state.sockets[peer_idx].rx_len = state.sockets[peer_idx].rx_len.saturating_add(write_len);
         return write_len as i64;
@@ -12930,15 +13516,10 @@ fn linux_socket_recv_payload(state: &mut LinuxShimState, sock_idx: usize, buf: u
         return linux_neg_errno(11); // EAGAIN
     }
     let read_len = available.min(len.min(i64::MAX as u64) as usize);
-    unsafe {
-        ptr::copy_nonoverlapping(
-            state.sockets[sock_idx]
-                .rx_buf
-                .as_ptr()
-                .add(state.sockets[sock_idx].rx_cursor),
-            buf as *mut u8,
-            read_len,
-        );
+    let rx_cursor = state.sockets[sock_idx].rx_cursor;
+    let src = &state.sockets[sock_idx].rx_buf[rx_cursor..rx_cursor + read_len];
+    if crate::uaccess::copy_to_user(buf, src).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
     state.sockets[sock_idx].rx_cursor = state.sockets[sock_idx].rx_cursor.saturating_add(read_len);
     if state.sockets[sock_idx].rx_cursor >= state.sockets[sock_idx].rx_len {
@@ -13127,10 +13708,8 @@ fn linux_sys_socketpair(
         aux: 0,
     };
     state.open_file_count = state.open_file_count.saturating_add(2);
-    unsafe {
-        let out = sv_ptr as *mut i32;
-        ptr::write(out, fd_a);
-        ptr::write(out.add(1), fd_b);
+    if crate::uaccess::write_user(sv_ptr, [fd_a, fd_b]).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
     0
 }
@@ -13149,7 +13728,9 @@ fn linux_sys_connect(state: &mut LinuxShimState, fd: u64, addr_ptr: u64, addr_le
         state.last_unix_connect_errno = 22;
         return linux_neg_errno(22);
     }
-    let addr_family = unsafe { ptr::read(addr_ptr as *const u16) };
+    let Ok(addr_family) = crate::uaccess::read_user::<u16>(addr_ptr) else {
+        return linux_neg_errno(14); // EFAULT
+    };
     let sock_domain = state.sockets[sock_idx].domain;
     if sock_domain == LINUX_AF_UNIX && addr_family != LINUX_AF_UNIX {
         state.sockets[sock_idx].last_error = 97;
@@ -13303,7 +13884,9 @@ fn linux_sys_bind(state: &mut LinuxShimState, fd: u64, addr_ptr: u64, addr_len:
     if state.sockets[sock_idx].domain != LINUX_AF_UNIX {
         return linux_neg_errno(97); // EAFNOSUPPORT
     }
-    let family = unsafe { ptr::read(addr_ptr as *const u16) };
+    let Ok(family) = crate::uaccess::read_user::<u16>(addr_ptr) else {
+        return linux_neg_errno(14); // EFAULT
+    };
     if family != LINUX_AF_UNIX {
         return linux_neg_errno(97); // EAFNOSUPPORT
     }
@@ -13391,7 +13974,10 @@ fn linux_sys_accept(state: &mut LinuxShimState, fd: u64, addr_ptr: u64, addr_len
     state.sockets[sock_idx].pending_accept_index = -1;
 
     if addr_ptr != 0 && addr_len_ptr != 0 {
-        let req = unsafe { ptr::read(addr_len_ptr as *const u32) } as usize;
+        let Ok(req) = crate::uaccess::read_user::<u32>(addr_len_ptr) else {
+            return linux_neg_errno(14); // EFAULT
+        };
+        let req = req as usize;
         if req >= core::mem::size_of::<LinuxSockAddrUn>() {
             let mut out = LinuxSockAddrUn {
                 family: LINUX_AF_UNIX,
@@ -13403,14 +13989,16 @@ fn linux_sys_accept(state: &mut LinuxShimState, fd: u64, addr_ptr: u64, addr_len
                 out.path[i] = state.sockets[pending_idx].path[i];
                 i += 1;
             }
-            unsafe {
-                ptr::write(addr_ptr as *mut LinuxSockAddrUn, out);
-                ptr::write(addr_len_ptr as *mut u32, core::mem::size_of::<LinuxSockAddrUn>() as u32);
+            if crate::uaccess::write_user(addr_ptr, out).is_err()
+                || crate::uaccess::write_user(addr_len_ptr, core::mem::size_of::<LinuxSockAddrUn>() as u32).is_err()
+            {
+                return linux_neg_errno(14); // EFAULT
             }
         } else if req >= core::mem::size_of::<u16>() {
-            unsafe {
-                ptr::write(addr_ptr as *mut u16, LINUX_AF_UNIX);
-                ptr::write(addr_len_ptr as *mut u32, core::mem::size_of::<u16>() as u32);
+            if crate::uaccess::write_user(addr_ptr, LINUX_AF_UNIX).is_err()
+                || crate::uaccess::write_user(addr_len_ptr, core::mem::size_of::<u16>() as u32).is_err()
+            {
+                return linux_neg_errno(14); // EFAULT
             }
         } else {
             return linux_neg_errno(22); // EINVAL
@@ -13493,7 +14081,9 @@ fn linux_sys_sendmsg(state: &mut LinuxShimState, fd: u64, msg_ptr: u64, _flags:
     if msg_ptr == 0 {
         return linux_neg_errno(14); // EFAULT
     }
-    let msg = unsafe { ptr::read(msg_ptr as *const LinuxMsgHdr) };
+    let Ok(msg) = crate::uaccess::read_user::<LinuxMsgHdr>(msg_ptr) else {
+        return linux_neg_errno(14); // EFAULT
+    };
     if msg.msg_iov == 0 || msg.msg_iovlen == 0 {
         return 0;
     }
@@ -13542,101 +14132,104 @@ fn linux_sys_sendmsg(state: &mut LinuxShimState, fd: u64, msg_ptr: u64, _flags:
         let mut chunk = [0u8; 4096];
         let mut total = 0u64;
         let mut i = 0usize;
-        unsafe {
-            let iov_ptr = msg.msg_iov as *const LinuxIovec;
-            while i < count {
-                let iov = ptr::read(iov_ptr.add(i));
-                if iov.len > 0 && iov.base == 0 {
-                    if held_count > 0 {
-                        linux_socket_rights_release_open_slots(state, &held_rights, held_count);
-                    }
-                    if total == 0 {
-                        return linux_neg_errno(14); // EFAULT
-                    }
+        while i < count {
+            let entry = msg.msg_iov + (i * core::mem::size_of::<LinuxIovec>()) as u64;
+            let iov = crate::uaccess::read_user::<LinuxIovec>(entry)
+                .ok()
+                .filter(|iov| iov.len == 0 || iov.base != 0);
+            let Some(iov) = iov else {
+                if held_count > 0 {
+                    linux_socket_rights_release_open_slots(state, &held_rights, held_count);
+                }
+                if total == 0 {
+                    return linux_neg_errno(14); // EFAULT
+                }
+                break;
+            };
+            let mut off = 0u64;
+            while off < iov.len {
+                let copy_len = iov.len.saturating_sub(off).min(chunk.len() as u64) as usize;
+                if crate::uaccess::copy_from_user(&mut chunk[..copy_len], iov.base.saturating_add(off)).is_err() {
                     break;
                 }
-                let mut off = 0u64;
-                while off < iov.len {
-                    let copy_len = iov
-                        .len
-                        .saturating_sub(off)
-                        .min(chunk.len() as u64) as usize;
-                    ptr::copy_nonoverlapping(
-                        iov.base.saturating_add(off) as *const u8,
-                        chunk.as_mut_ptr(),
-                        copy_len,
-                    );
-                    linux_x11_consume_payload(state, sock_idx, &chunk[..copy_len]);
-                    total = total.saturating_add(copy_len as u64);
-                    off = off.saturating_add(copy_len as u64);
+                linux_x11_consume_payload(state, sock_idx, &chunk[..copy_len]);
+                total = total.saturating_add(copy_len as u64);
+                off = off.saturating_add(copy_len as u64);
+            }
+            if off < iov.len {
+                if total == 0 {
+                    return linux_neg_errno(14); // EFAULT
                 }
-                i += 1;
+                break;
             }
+            i += 1;
         }
         total.min(i64::MAX as u64) as i64
     } else {
         let mut total = 0u64;
         let mut i = 0usize;
-        unsafe {
-            let iov_ptr = msg.msg_iov as *const LinuxIovec;
-            while i < count {
-                let iov = ptr::read(iov_ptr.add(i));
-                if endpoint == LINUX_SOCKET_ENDPOINT_WAYLAND
-                    && held_count > 0
-                    && !wayland_rights_queued
-                    && iov.len > 0
-                {
-                    if iov.base == 0 {
-                        linux_socket_rights_release_open_slots(state, &held_rights, held_count);
-                        if total == 0 {
-                            return linux_neg_errno(14); // EFAULT
-                        }
-                        break;
-                    }
-                    let mut rights_msg = LinuxSocketRightsMsg::empty();
-                    rights_msg.active = true;
-                    rights_msg.fd_count = held_count as u8;
-                    let mut r = 0usize;
-                    while r < held_count {
-                        rights_msg.open_slot_indices[r] = held_rights[r] as u16;
-                        r += 1;
-                    }
-                    if !linux_socket_rights_push_message(state, sock_idx, rights_msg) {
+        while i < count {
+            let entry = msg.msg_iov + (i * core::mem::size_of::<LinuxIovec>()) as u64;
+            let Ok(iov) = crate::uaccess::read_user::<LinuxIovec>(entry) else {
+                if total == 0 {
+                    if held_count > 0 && !wayland_rights_queued {
                         linux_socket_rights_release_open_slots(state, &held_rights, held_count);
-                        if total == 0 {
-                            return linux_neg_errno(11); // EAGAIN
-                        }
-                        break;
                     }
-                    wayland_rights_queued = true;
+                    return linux_neg_errno(14); // EFAULT
                 }
-                let res = linux_sys_sendto(
-                    state,
-                    fd,
-                    iov.base,
-                    iov.len,
-                    0,
-                    msg.msg_name,
-                    msg.msg_namelen as u64,
-                );
-                if res < 0 {
+                break;
+            };
+            if endpoint == LINUX_SOCKET_ENDPOINT_WAYLAND && held_count > 0 && !wayland_rights_queued && iov.len > 0 {
+                if iov.base == 0 {
+                    linux_socket_rights_release_open_slots(state, &held_rights, held_count);
                     if total == 0 {
-                        if held_count > 0 {
-                            if endpoint != LINUX_SOCKET_ENDPOINT_WAYLAND || !wayland_rights_queued {
-                                linux_socket_rights_release_open_slots(state, &held_rights, held_count);
-                            }
-                        }
-                        return res;
+                        return linux_neg_errno(14); // EFAULT
                     }
                     break;
                 }
-                let sent = res as u64;
-                total = total.saturating_add(sent);
-                if sent < iov.len {
+                let mut rights_msg = LinuxSocketRightsMsg::empty();
+                rights_msg.active = true;
+                rights_msg.fd_count = held_count as u8;
+                let mut r = 0usize;
+                while r < held_count {
+                    rights_msg.open_slot_indices[r] = held_rights[r] as u16;
+                    r += 1;
+                }
+                if !linux_socket_rights_push_message(state, sock_idx, rights_msg) {
+                    linux_socket_rights_release_open_slots(state, &held_rights, held_count);
+                    if total == 0 {
+                        return linux_neg_errno(11); // EAGAIN
+                    }
                     break;
                 }
-                i += 1;
+                wayland_rights_queued = true;
             }
+            let res = linux_sys_sendto(
+                state,
+                fd,
+                iov.base,
+                iov.len,
+                0,
+                msg.msg_name,
+                msg.msg_namelen as u64,
+            );
+            if res < 0 {
+                if total == 0 {
+                    if held_count > 0 {
+                        if endpoint != LINUX_SOCKET_ENDPOINT_WAYLAND || !wayland_rights_queued {
+                            linux_socket_rights_release_open_slots(state, &held_rights, held_count);
+                        }
+                    }
+                    return res;
+                }
+                break;
+            }
+            let sent = res as u64;
+            total = total.saturating_add(sent);
+            if sent < iov.len {
+                break;
+            }
+            i += 1;
         }
         total.min(i64::MAX as u64) as i64
     };
@@ -13674,7 +14267,9 @@ fn linux_sys_recvmsg(state: &mut LinuxShimState, fd: u64, msg_ptr: u64, flags: u
     if msg_ptr == 0 {
         return linux_neg_errno(14);
     }
-    let mut msg = unsafe { ptr::read(msg_ptr as *const LinuxMsgHdr) };
+    let Ok(mut msg) = crate::uaccess::read_user::<LinuxMsgHdr>(msg_ptr) else {
+        return linux_neg_errno(14); // EFAULT
+    };
     if msg.msg_iov == 0 || msg.msg_iovlen == 0 {
         return 0;
     }
@@ -13690,24 +14285,27 @@ fn linux_sys_recvmsg(state: &mut LinuxShimState, fd: u64, msg_ptr: u64, flags: u
     let count = (msg.msg_iovlen as usize).min(1024);
     let mut total = 0u64;
     let mut i = 0usize;
-    unsafe {
-        let iov_ptr = msg.msg_iov as *const LinuxIovec;
-        while i < count {
-            let iov = ptr::read(iov_ptr.add(i));
-            let res = linux_sys_recvfrom(state, fd, iov.base, iov.len, 0, 0, 0);
-            if res < 0 {
-                if total == 0 {
-                    return res;
-                }
-                break;
+    while i < count {
+        let entry = msg.msg_iov + (i * core::mem::size_of::<LinuxIovec>()) as u64;
+        let Ok(iov) = crate::uaccess::read_user::<LinuxIovec>(entry) else {
+            if total == 0 {
+                return linux_neg_errno(14); // EFAULT
             }
-            let got = res as u64;
-            total = total.saturating_add(got);
-            if got < iov.len {
-                break;
+            break;
+        };
+        let res = linux_sys_recvfrom(state, fd, iov.base, iov.len, 0, 0, 0);
+        if res < 0 {
+            if total == 0 {
+                return res;
             }
-            i += 1;
+            break;
+        }
+        let got = res as u64;
+        total = total.saturating_add(got);
+        if got < iov.len {
+            break;
         }
+        i += 1;
     }
 
     if total > 0 {
@@ -13722,8 +14320,8 @@ fn linux_sys_recvmsg(state: &mut LinuxShimState, fd: u64, msg_ptr: u64, flags: u
         msg.msg_controllen = 0;
     }
 
-    unsafe {
-        ptr::write(msg_ptr as *mut LinuxMsgHdr, msg);
+    if crate::uaccess::write_user(msg_ptr, msg).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
 
     total.min(i64::MAX as u64) as i64
@@ -13738,8 +14336,10 @@ fn linux_sys_getsockname(state: &mut LinuxShimState, fd: u64, addr_ptr: u64, add
     if addr_ptr == 0 || addr_len_ptr == 0 {
         return linux_neg_errno(14);
     }
-    let mut out_len = unsafe { ptr::read(addr_len_ptr as *const u32) } as usize;
-    if out_len < core::mem::size_of::<LinuxSockAddr>() {
+    let Ok(out_len) = crate::uaccess::read_user::<u32>(addr_len_ptr) else {
+        return linux_neg_errno(14); // EFAULT
+    };
+    if (out_len as usize) < core::mem::size_of::<LinuxSockAddr>() {
         return linux_neg_errno(22);
     }
     let family = state.sockets[sock_idx].domain;
@@ -13747,9 +14347,10 @@ fn linux_sys_getsockname(state: &mut LinuxShimState, fd: u64, addr_ptr: u64, add
         family,
         data: [0; 14],
     };
-    unsafe {
-        ptr::write(addr_ptr as *mut LinuxSockAddr, out);
-        ptr::write(addr_len_ptr as *mut u32, core::mem::size_of::<LinuxSockAddr>() as u32);
+    if crate::uaccess::write_user(addr_ptr, out).is_err()
+        || crate::uaccess::write_user(addr_len_ptr, core::mem::size_of::<LinuxSockAddr>() as u32).is_err()
+    {
+        return linux_neg_errno(14); // EFAULT
     }
     0
 }
@@ -13794,14 +14395,18 @@ fn linux_sys_setsockopt(
                 if optlen < core::mem::size_of::<i32>() as u64 {
                     return linux_neg_errno(22); // EINVAL
                 }
-                let _value = unsafe { ptr::read(optval as *const i32) };
+                if crate::uaccess::read_user::<i32>(optval).is_err() {
+                    return linux_neg_errno(14); // EFAULT
+                }
                 0
             }
             LINUX_SO_SNDTIMEO | LINUX_SO_RCVTIMEO => {
                 if optlen < core::mem::size_of::<LinuxTimeval>() as u64 {
                     return linux_neg_errno(22); // EINVAL
                 }
-                let _tv = unsafe { ptr::read(optval as *const LinuxTimeval) };
+                if crate::uaccess::read_user::<LinuxTimeval>(optval).is_err() {
+                    return linux_neg_errno(14); // EFAULT
+                }
                 0
             }
             _ => linux_neg_errno(92), // ENOPROTOOPT
@@ -13811,7 +14416,9 @@ fn linux_sys_setsockopt(
             if optlen < core::mem::size_of::<i32>() as u64 {
                 return linux_neg_errno(22);
             }
-            let _value = unsafe { ptr::read(optval as *const i32) };
+            if crate::uaccess::read_user::<i32>(optval).is_err() {
+                return linux_neg_errno(14); // EFAULT
+            }
             0
         } else {
             linux_neg_errno(92)
@@ -13837,7 +14444,10 @@ fn linux_sys_getsockopt(
     if optval == 0 || optlen_ptr == 0 {
         return linux_neg_errno(14);
     }
-    let req_len = unsafe { ptr::read(optlen_ptr as *const u32) } as usize;
+    let Ok(req_len) = crate::uaccess::read_user::<u32>(optlen_ptr) else {
+        return linux_neg_errno(14); // EFAULT
+    };
+    let req_len = req_len as usize;
     if level == LINUX_SOL_SOCKET {
         if optname == LINUX_SO_PEERCRED {
             if req_len < core::mem::size_of::<LinuxUcred>() {
@@ -13849,9 +14459,10 @@ fn linux_sys_getsockopt(
                 1
             };
             let cred = LinuxUcred { pid, uid: 0, gid: 0 };
-            unsafe {
-                ptr::write(optval as *mut LinuxUcred, cred);
-                ptr::write(optlen_ptr as *mut u32, core::mem::size_of::<LinuxUcred>() as u32);
+            if crate::uaccess::write_user(optval, cred).is_err()
+                || crate::uaccess::write_user(optlen_ptr, core::mem::size_of::<LinuxUcred>() as u32).is_err()
+            {
+                return linux_neg_errno(14); // EFAULT
             }
             return 0;
         }
@@ -13873,9 +14484,10 @@ fn linux_sys_getsockopt(
             LINUX_SO_DOMAIN => state.sockets[sock_idx].domain as i32,
             _ => return linux_neg_errno(92), // ENOPROTOOPT
         };
-        unsafe {
-            ptr::write(optval as *mut i32, value);
-            ptr::write(optlen_ptr as *mut u32, core::mem::size_of::<i32>() as u32);
+        if crate::uaccess::write_user(optval, value).is_err()
+            || crate::uaccess::write_user(optlen_ptr, core::mem::size_of::<i32>() as u32).is_err()
+        {
+            return linux_neg_errno(14); // EFAULT
         }
         return 0;
     }
@@ -13886,9 +14498,10 @@ fn linux_sys_getsockopt(
         if req_len < core::mem::size_of::<i32>() {
             return linux_neg_errno(22);
         }
-        unsafe {
-            ptr::write(optval as *mut i32, 1);
-            ptr::write(optlen_ptr as *mut u32, core::mem::size_of::<i32>() as u32);
+        if crate::uaccess::write_user(optval, 1i32).is_err()
+            || crate::uaccess::write_user(optlen_ptr, core::mem::size_of::<i32>() as u32).is_err()
+        {
+            return linux_neg_errno(14); // EFAULT
         }
         return 0;
     }
@@ -13970,7 +14583,9 @@ fn linux_sys_epoll_ctl(state: &mut LinuxShimState, epfd: u64, op: u64, fd: u64,
             if event_ptr == 0 {
                 return linux_neg_errno(14); // EFAULT
             }
-            let ev = unsafe { ptr::read(event_ptr as *const LinuxEpollEvent) };
+            let Ok(ev) = crate::uaccess::read_user::<LinuxEpollEvent>(event_ptr) else {
+                return linux_neg_errno(14); // EFAULT
+            };
             let mut free_slot = None;
             let mut j = 0usize;
             while j < LINUX_MAX_EPOLL_WATCHES {
@@ -14005,7 +14620,9 @@ fn linux_sys_epoll_ctl(state: &mut LinuxShimState, epfd: u64, op: u64, fd: u64,
             if event_ptr == 0 {
                 return linux_neg_errno(14);
             }
-            let ev = unsafe { ptr::read(event_ptr as *const LinuxEpollEvent) };
+            let Ok(ev) = crate::uaccess::read_user::<LinuxEpollEvent>(event_ptr) else {
+                return linux_neg_errno(14); // EFAULT
+            };
             state.epolls[ep_idx].watches[idx].events = ev.events;
             state.epolls[ep_idx].watches[idx].data = ev.data;
             0
@@ -14047,29 +14664,27 @@ fn linux_sys_epoll_pwait(
     }
 
     let mut count = 0usize;
-    unsafe {
-        let out = events_ptr as *mut LinuxEpollEvent;
-        let mut i = 0usize;
-        while i < LINUX_MAX_EPOLL_WATCHES && count < max_out {
-            let watch = state.epolls[ep_idx].watches[i];
-            if watch.active {
-                let poll_mask = linux_epoll_events_to_poll(watch.events);
-                let poll_ready = linux_poll_ready_mask(state, watch.target_fd, poll_mask);
-                let ep_ready = linux_poll_to_epoll_events(poll_ready) & watch.events;
-                if ep_ready != 0 {
-                    ptr::write(
-                        out.add(count),
-                        LinuxEpollEvent {
-                            events: ep_ready,
-                            _pad: 0,
-                            data: watch.data,
-                        },
-                    );
-                    count += 1;
+    let mut i = 0usize;
+    while i < LINUX_MAX_EPOLL_WATCHES && count < max_out {
+        let watch = state.epolls[ep_idx].watches[i];
+        if watch.active {
+            let poll_mask = linux_epoll_events_to_poll(watch.events);
+            let poll_ready = linux_poll_ready_mask(state, watch.target_fd, poll_mask);
+            let ep_ready = linux_poll_to_epoll_events(poll_ready) & watch.events;
+            if ep_ready != 0 {
+                let event = LinuxEpollEvent {
+                    events: ep_ready,
+                    _pad: 0,
+                    data: watch.data,
+                };
+                let entry = events_ptr + (count * core::mem::size_of::<LinuxEpollEvent>()) as u64;
+                if crate::uaccess::write_user(entry, event).is_err() {
+                    return linux_neg_errno(14); // EFAULT
                 }
+                count += 1;
             }
-            i += 1;
         }
+        i += 1;
     }
     count as i64
 }
@@ -14086,7 +14701,9 @@ fn linux_sys_epoll_pwait2(
     let timeout_ms = if timeout_ptr == 0 {
         -1
     } else {
-        let ts = unsafe { ptr::read(timeout_ptr as *const LinuxTimespec) };
+        let Ok(ts) = crate::uaccess::read_user::<LinuxTimespec>(timeout_ptr) else {
+            return linux_neg_errno(14); // EFAULT
+        };
         if ts.tv_sec < 0 || ts.tv_nsec < 0 || ts.tv_nsec >= 1_000_000_000 {
             return linux_neg_errno(22); // EINVAL
         }
@@ -14189,18 +14806,18 @@ fn linux_sys_timerfd_settime(
     if open.object_index >= LINUX_MAX_EVENTFDS || !state.eventfds[open.object_index].active {
         return linux_neg_errno(9);
     }
+    let Ok(new_spec) = crate::uaccess::read_user::<LinuxItimerSpec>(new_value_ptr) else {
+        return linux_neg_errno(14); // EFAULT
+    };
     if old_value_ptr != 0 {
-        unsafe {
-            ptr::write(
-                old_value_ptr as *mut LinuxItimerSpec,
-                LinuxItimerSpec {
-                    it_interval: LinuxTimespec { tv_sec: 0, tv_nsec: 0 },
-                    it_value: LinuxTimespec { tv_sec: 0, tv_nsec: 0 },
-                },
-            );
+        let old_spec = LinuxItimerSpec {
+            it_interval: LinuxTimespec { tv_sec: 0, tv_nsec: 0 },
+            it_value: LinuxTimespec { tv_sec: 0, tv_nsec: 0 },
+        };
+        if crate::uaccess::write_user(old_value_ptr, old_spec).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
     }
-    let new_spec = unsafe { ptr::read(new_value_ptr as *const LinuxItimerSpec) };
     if new_spec.it_value.tv_sec == 0 && new_spec.it_value.tv_nsec == 0 {
         state.eventfds[open.object_index].counter = 0;
     } else {
@@ -14236,14 +14853,14 @@ fn linux_sys_timerfd_gettime(state: &LinuxShimState, fd: u64, curr_value_ptr: u6
             LinuxTimespec { tv_sec: 0, tv_nsec: 0 }
         },
     };
-    unsafe {
-        ptr::write(curr_value_ptr as *mut LinuxItimerSpec, spec);
+    if crate::uaccess::write_user(curr_value_ptr, spec).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
     0
 }
 
 fn linux_sys_pipe2(state: &mut LinuxShimState, pipefd_ptr: u64, flags: u64) -> i64 {
-    if pipefd_ptr == 0 {
+    if crate::uaccess::check(pipefd_ptr, 2 * core::mem::size_of::<i32>()).is_err() {
         return linux_neg_errno(14); // EFAULT
     }
     let allowed = LINUX_O_NONBLOCK | LINUX_DUP3_CLOEXEC;
@@ -14294,10 +14911,8 @@ fn linux_sys_pipe2(state: &mut LinuxShimState, pipefd_ptr: u64, flags: u64) -> i
         aux: 0,
     };
     state.open_file_count = state.open_file_count.saturating_add(2);
-    unsafe {
-        let out = pipefd_ptr as *mut i32;
-        ptr::write(out, read_fd);
-        ptr::write(out.add(1), write_fd);
+    if crate::uaccess::write_user(pipefd_ptr, [read_fd, write_fd]).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
     0
 }
@@ -14374,23 +14989,19 @@ fn linux_sys_sched_getaffinity(_pid: u64, cpusetsize: u64, mask_ptr: u64) -> i64
         return linux_neg_errno(14); // EFAULT
     }
     let out_len = (cpusetsize as usize).min(core::mem::size_of::<u64>());
-    unsafe {
-        ptr::write_bytes(mask_ptr as *mut u8, 0, out_len);
-        ptr::write(mask_ptr as *mut u8, 1u8);
+    let mask = 1u64.to_le_bytes();
+    if crate::uaccess::copy_to_user(mask_ptr, &mask[..out_len]).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
     out_len as i64
 }
 
 fn linux_sys_getcpu(cpu_ptr: u64, node_ptr: u64, _cache_ptr: u64) -> i64 {
-    if cpu_ptr != 0 {
-        unsafe {
-            ptr::write(cpu_ptr as *mut u32, 0);
-        }
+    if cpu_ptr != 0 && crate::uaccess::write_user(cpu_ptr, 0u32).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
-    if node_ptr != 0 {
-        unsafe {
-            ptr::write(node_ptr as *mut u32, 0);
-        }
+    if node_ptr != 0 && crate::uaccess::write_user(node_ptr, 0u32).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
     0
 }
@@ -14504,10 +15115,10 @@ fn linux_write_statx_mode(buf: u64, size: u64, mode: u16) -> i64 {
         stx_dio_offset_align: 0,
         __spare3: [0; 12],
     };
-    unsafe {
-        ptr::write(buf as *mut LinuxStatx, stx);
+    match crate::uaccess::write_user(buf, stx) {
+        Ok(()) => 0,
+        Err(_) => linux_neg_errno(14), // EFAULT
     }
-    0
 }
 
 fn linux_write_statx(buf: u64, size: u64) -> i64 {
@@ -14570,6 +15181,83 @@ fn linux_sys_membarrier(cmd: u64, _flags: u64, _cpu_id: u64) -> i64 {
     0
 }
 
+/// Paths below a volume attached with `mount` (another disk, `/host`) are
+/// served by `crate::fs::vfs` instead of the shim's own lookup.
+fn linux_mounted_path(path: &[u8], path_len: usize) -> Option<&str> {
+    let text = core::str::from_utf8(&path[..path_len]).ok()?;
+    match crate::fs::vfs::mount_kind(text) {
+        Some(crate::fs::vfs::FsKind::Fat | crate::fs::vfs::FsKind::Host) => Some(text),
+        _ => None,
+    }
+}
+
+fn linux_open_mounted(
+    state: &mut LinuxShimState,
+    normalized: &[u8; LINUX_PATH_MAX],
+    path_len: usize,
+    flags: u64,
+) -> i64 {
+    let Some(path) = linux_mounted_path(normalized, path_len) else {
+        return linux_neg_errno(2); // ENOENT
+    };
+    let access = flags & (LINUX_O_WRONLY | LINUX_O_RDWR);
+    let vfs_flags = crate::fs::vfs::OpenFlags {
+        write: access != 0,
+        create: (flags & LINUX_O_CREAT) != 0,
+        truncate: access != 0 && (flags & LINUX_O_TRUNC) != 0,
+        append: (flags & LINUX_O_APPEND) != 0,
+    };
+    let existing = crate::fs::vfs::stat(path).ok();
+    let object = match existing {
+        None if !vfs_flags.create => Err(linux_neg_errno(2)), // ENOENT
+        Some(_) if vfs_flags.create && (flags & LINUX_O_EXCL) != 0 => Err(linux_neg_errno(17)), // EEXIST
+        Some(stat) if stat.is_dir && vfs_flags.write => Err(linux_neg_errno(21)), // EISDIR
+        Some(stat) if stat.is_dir => linux_allocate_dir_slot(state, normalized, path_len)
+            .map(|dir_idx| (LINUX_OPEN_KIND_DIR, dir_idx))
+            .ok_or(linux_neg_errno(24)),
+        _ if (flags & LINUX_O_DIRECTORY) != 0 => Err(linux_neg_errno(20)), // ENOTDIR
+        _ => crate::fs::vfs::open(path, vfs_flags)
+            .map(|handle| (LINUX_OPEN_KIND_VFS, handle.id() as usize))
+            .map_err(|_| linux_neg_errno(13)), // EACCES
+    };
+    let (kind, object_index) = match object {
+        Ok(v) => v,
+        Err(err) => {
+            linux_record_last_path_lookup(state, LINUX_SYS_OPENAT, normalized, path_len, err, existing.is_some());
+            return err;
+        }
+    };
+
+    let slot_idx = linux_find_unused_fd(state, state.next_fd)
+        .and_then(|fd| linux_allocate_open_slot_for_fd(state, fd).map(|idx| (fd, idx)));
+    let Some((fd, open_idx)) = slot_idx else {
+        if kind == LINUX_OPEN_KIND_VFS {
+            let _ = crate::fs::vfs::Handle::from_id(object_index as u64).close();
+        }
+        return linux_neg_errno(24); // EMFILE
+    };
+    let mut open_slot = LinuxOpenFileSlot::empty();
+    open_slot.active = true;
+    open_slot.fd = fd;
+    open_slot.kind = kind;
+    open_slot.object_index = object_index;
+    open_slot.flags = flags;
+    if (flags & LINUX_O_CLOEXEC) != 0 {
+        open_slot.flags |= LINUX_DUP3_CLOEXEC;
+    }
+    if kind == LINUX_OPEN_KIND_VFS && vfs_flags.append {
+        open_slot.cursor = crate::fs::vfs::Handle::from_id(object_index as u64)
+            .stat()
+            .map(|stat| stat.size)
+            .unwrap_or(0);
+    }
+    state.open_files[open_idx] = open_slot;
+    state.open_file_count = state.open_file_count.saturating_add(1);
+    let result = fd as i64;
+    linux_record_last_path_lookup(state, LINUX_SYS_OPENAT, normalized, path_len, result, true);
+    result
+}
+
 fn linux_sys_openat(
     state: &mut LinuxShimState,
     dirfd: u64,
@@ -14588,6 +15276,9 @@ fn linux_sys_openat(
         Ok(v) => v,
         Err(err) => return err,
     };
+    if linux_mounted_path(&normalized, path_len).is_some() {
+        return linux_open_mounted(state, &normalized, path_len, flags);
+    }
 
     let wants_create = (flags & LINUX_O_CREAT) != 0;
     let wants_excl = (flags & LINUX_O_EXCL) != 0;
@@ -14621,7 +15312,7 @@ fn linux_sys_openat(
             let filename = core::str::from_utf8(&normalized[last_slash + 1..path_len]).unwrap_or("NEWFILE");
             if let Some(parent_meta) = linux_fat_lookup_guest_path(parent_path, parent_path.len()) {
                 unsafe {
-                    let fat = &mut crate::fat32::GLOBAL_FAT;
+                    let fat = crate::fat32::GLOBAL_FAT.get_mut();
                     let _ = fat.write_text_file_in_dir(parent_meta.cluster, filename, &[]);
                 }
             }
@@ -14750,15 +15441,9 @@ fn linux_sys_openat2(
         return linux_neg_errno(22); // EINVAL
     }
 
-    let mut how = LinuxOpenHow::empty();
-    let copy_len = (size as usize).min(core::mem::size_of::<LinuxOpenHow>());
-    unsafe {
-        ptr::copy_nonoverlapping(
-            how_ptr as *const u8,
-            (&mut how as *mut LinuxOpenHow) as *mut u8,
-            copy_len,
-        );
-    }
+    let Ok(how) = crate::uaccess::read_user::<LinuxOpenHow>(how_ptr) else {
+        return linux_neg_errno(14); // EFAULT
+    };
 
     // Resolve constraints are currently ignored by this shim and treated as best-effort openat.
     let _resolve = how.resolve;
@@ -14770,15 +15455,15 @@ fn linux_sys_read(state: &mut LinuxShimState, fd: u64, buf: u64, len: u64) -> i6
     if fd_i < 0 {
         return linux_neg_errno(9); // EBADF
     }
-    if fd_i == 0 {
-        return 0;
-    }
     if len == 0 {
         return 0;
     }
     if buf == 0 {
         return linux_neg_errno(14); // EFAULT
     }
+    if fd_i == 0 {
+        return linux_sys_read_stdin(buf, len);
+    }
     let Some(open_idx) = linux_find_open_slot_index(state, fd_i as i32) else {
         return linux_neg_errno(9); // EBADF
     };
@@ -14809,12 +15494,11 @@ fn linux_sys_read(state: &mut LinuxShimState, fd: u64, buf: u64, len: u64) -> i6
             if to_copy == 0 {
                 return 0;
             }
-            unsafe {
-                ptr::copy_nonoverlapping(
-                    (runtime.data_ptr.saturating_add(cursor)) as *const u8,
-                    buf as *mut u8,
-                    to_copy as usize,
-                );
+            let src = unsafe {
+                core::slice::from_raw_parts(runtime.data_ptr.saturating_add(cursor) as *const u8, to_copy as usize)
+            };
+            if crate::uaccess::copy_to_user(buf, src).is_err() {
+                return linux_neg_errno(14); // EFAULT
             }
             state.open_files[open_idx].cursor = cursor.saturating_add(to_copy);
             to_copy as i64
@@ -14830,15 +15514,30 @@ fn linux_sys_read(state: &mut LinuxShimState, fd: u64, buf: u64, len: u64) -> i6
             read_buf.resize(to_read, 0);
             
             unsafe {
-                let fat = &mut crate::fat32::GLOBAL_FAT;
+                let fat = crate::fat32::GLOBAL_FAT.get_mut();
                 let read_len = fat.read_file_range(cluster, usize::MAX, cursor as usize, &mut read_buf).unwrap_or(0);
                 if read_len > 0 {
-                    ptr::copy_nonoverlapping(read_buf.as_ptr(), buf as *mut u8, read_len);
+                    if crate::uaccess::copy_to_user(buf, &read_buf[..read_len]).is_err() {
+                        return linux_neg_errno(14); // EFAULT
+                    }
                     state.open_files[open_idx].cursor = cursor.saturating_add(read_len as u64);
                 }
                 read_len as i64
             }
         }
+        LINUX_OPEN_KIND_VFS => {
+            let cursor = slot.cursor;
+            let mut read_buf = alloc::vec![0u8; len.min(LINUX_VFS_IO_CHUNK) as usize];
+            let handle = crate::fs::vfs::Handle::from_id(slot.object_index as u64);
+            let Ok(read_len) = handle.read_at(cursor, &mut read_buf) else {
+                return linux_neg_errno(5); // EIO
+            };
+            if crate::uaccess::copy_to_user(buf, &read_buf[..read_len]).is_err() {
+                return linux_neg_errno(14); // EFAULT
+            }
+            state.open_files[open_idx].cursor = cursor.saturating_add(read_len as u64);
+            read_len as i64
+        }
         LINUX_OPEN_KIND_DIR => linux_neg_errno(21), // EISDIR
         LINUX_OPEN_KIND_EVENTFD => {
             if len < 8 {
@@ -14852,16 +15551,15 @@ fn linux_sys_read(state: &mut LinuxShimState, fd: u64, buf: u64, len: u64) -> i6
             if counter == 0 {
                 return linux_neg_errno(11); // EAGAIN
             }
-            let value = if state.eventfds[event_idx].semaphore {
-                state.eventfds[event_idx].counter = counter.saturating_sub(1);
-                1u64
+            let (value, left) = if state.eventfds[event_idx].semaphore {
+                (1u64, counter.saturating_sub(1))
             } else {
-                state.eventfds[event_idx].counter = 0;
-                counter
+                (counter, 0)
             };
-            unsafe {
-                ptr::write(buf as *mut u64, value);
+            if crate::uaccess::write_user(buf, value).is_err() {
+                return linux_neg_errno(14); // EFAULT
             }
+            state.eventfds[event_idx].counter = left;
             8
         }
         LINUX_OPEN_KIND_PIPE_READ => {
@@ -14877,8 +15575,8 @@ fn linux_sys_read(state: &mut LinuxShimState, fd: u64, buf: u64, len: u64) -> i6
                 return 0;
             }
             let to_read = pending.min(len).min(i64::MAX as u64);
-            unsafe {
-                ptr::write_bytes(buf as *mut u8, 0, to_read as usize);
+            if crate::uaccess::clear_user(buf, to_read as usize).is_err() {
+                return linux_neg_errno(14); // EFAULT
             }
             state.pipes[pipe_idx].pending_bytes = pending.saturating_sub(to_read);
             to_read as i64
@@ -14887,7 +15585,7 @@ fn linux_sys_read(state: &mut LinuxShimState, fd: u64, buf: u64, len: u64) -> i6
         LINUX_OPEN_KIND_STDIO_DUP => {
             let target = slot.aux as i32;
             if target == 0 {
-                0
+                linux_sys_read_stdin(buf, len)
             } else {
                 linux_neg_errno(9)
             }
@@ -14927,7 +15625,7 @@ fn linux_sys_lseek(state: &mut LinuxShimState, fd: u64, offset: u64, whence: u64
         let cluster = state.open_files[open_idx].object_index as u32;
         let size = if cluster >= 2 {
             unsafe {
-                let fat = &mut crate::fat32::GLOBAL_FAT;
+                let fat = crate::fat32::GLOBAL_FAT.get_mut();
                 fat.get_file_size(cluster).unwrap_or(0) as u64
             }
         } else {
@@ -14939,6 +15637,17 @@ fn linux_sys_lseek(state: &mut LinuxShimState, fd: u64, offset: u64, whence: u64
             LINUX_SEEK_END => size as i128,
             _ => return linux_neg_errno(22), // EINVAL
         }
+    } else if kind == LINUX_OPEN_KIND_VFS {
+        let handle = crate::fs::vfs::Handle::from_id(state.open_files[open_idx].object_index as u64);
+        let Ok(stat) = handle.stat() else {
+            return linux_neg_errno(9);
+        };
+        match whence {
+            LINUX_SEEK_SET => 0i128,
+            LINUX_SEEK_CUR => state.open_files[open_idx].cursor as i128,
+            LINUX_SEEK_END => stat.size as i128,
+            _ => return linux_neg_errno(22), // EINVAL
+        }
     } else {
         return linux_neg_errno(29); // ESPIPE
     };
@@ -14969,6 +15678,10 @@ fn linux_sys_fstat(state: &mut LinuxShimState, fd: u64, stat_ptr: u64) -> i64 {
             linux_write_stat64(stat_ptr, state.runtime_files[runtime_idx].size)
         }
         LINUX_OPEN_KIND_DIR => linux_write_stat64_mode(stat_ptr, 0, LINUX_STAT_MODE_DIR),
+        LINUX_OPEN_KIND_VFS => match crate::fs::vfs::Handle::from_id(slot.object_index as u64).stat() {
+            Ok(stat) => linux_write_stat64(stat_ptr, stat.size),
+            Err(_) => linux_neg_errno(9),
+        },
         LINUX_OPEN_KIND_EVENTFD
         | LINUX_OPEN_KIND_PIPE_READ
         | LINUX_OPEN_KIND_PIPE_WRITE
@@ -14998,6 +15711,16 @@ fn linux_sys_newfstatat(
         Ok(v) => v,
         Err(err) => return err,
     };
+    if let Some(mounted) = linux_mounted_path(&normalized, path_len) {
+        let stat = crate::fs::vfs::stat(mounted);
+        let result = match stat {
+            Ok(stat) if stat.is_dir => linux_write_stat64_mode(stat_ptr, 0, LINUX_STAT_MODE_DIR),
+            Ok(stat) => linux_write_stat64(stat_ptr, stat.size),
+            Err(_) => linux_neg_errno(2),
+        };
+        linux_record_last_path_lookup(state, LINUX_SYS_NEWFSTATAT, &normalized, path_len, result, stat.is_ok());
+        return result;
+    }
     let (exists, is_file, runtime_idx_opt, mode_bits, file_size) =
         linux_vfs_lookup_path(state, &normalized, path_len);
     let result = if !exists {
@@ -15070,14 +15793,16 @@ fn linux_mark_robust_futex_owner_died(state: &mut LinuxShimState, futex_uaddr: u
     if futex_uaddr == 0 || (futex_uaddr & 0x3) != 0 || exiting_tid == 0 {
         return;
     }
-    let cur = unsafe { ptr::read_volatile(futex_uaddr as *const u32) };
+    let Ok(cur) = crate::uaccess::read_user::<u32>(futex_uaddr) else {
+        return;
+    };
     if (cur & LINUX_FUTEX_TID_MASK) != exiting_tid {
         return;
     }
     let mut next = cur & !LINUX_FUTEX_TID_MASK;
     next |= LINUX_FUTEX_OWNER_DIED;
-    unsafe {
-        ptr::write_volatile(futex_uaddr as *mut u32, next);
+    if crate::uaccess::write_user(futex_uaddr, next).is_err() {
+        return;
     }
     let _ = linux_wake_futex_waiters(state, futex_uaddr, 1);
 }
@@ -15105,14 +15830,18 @@ fn linux_cleanup_thread_robust_list(
     if robust_head == 0 || robust_len < LINUX_ROBUST_LIST_HEAD_LEN_MIN || exiting_tid == 0 {
         return;
     }
-    let head = unsafe { ptr::read(robust_head as *const LinuxRobustListHead) };
+    let Ok(head) = crate::uaccess::read_user::<LinuxRobustListHead>(robust_head) else {
+        return;
+    };
     let mut node = head.list_next;
     let mut visited = 0usize;
     while node != 0 && node != robust_head && visited < LINUX_ROBUST_LIST_MAX_NODES {
         if let Some(futex_uaddr) = linux_robust_entry_futex_uaddr(node, head.futex_offset) {
             linux_mark_robust_futex_owner_died(state, futex_uaddr, exiting_tid);
         }
-        let next = unsafe { ptr::read(node as *const u64) };
+        let Ok(next) = crate::uaccess::read_user::<u64>(node) else {
+            break;
+        };
         if next == node {
             break;
         }
@@ -15132,9 +15861,8 @@ fn linux_cleanup_exiting_thread_sync(state: &mut LinuxShimState, slot: LinuxThre
         linux_cleanup_thread_robust_list(state, slot.robust_list_head, slot.robust_list_len, slot.tid);
     }
     if slot.tid_addr != 0 {
-        unsafe {
-            ptr::write(slot.tid_addr as *mut u32, 0);
-        }
+        // CLONE_CHILD_CLEARTID: a bad address is ignored, as on Linux.
+        let _ = crate::uaccess::write_user(slot.tid_addr, 0u32);
         let _ = linux_wake_futex_waiters(state, slot.tid_addr, 1);
     }
 }
@@ -15284,6 +16012,9 @@ fn linux_sys_mmap(
     if aligned_len > usize::MAX as u64 {
         return linux_neg_errno(12);
     }
+    if !crate::wx::permits((prot & LINUX_PROT_WRITE) != 0, (prot & LINUX_PROT_EXEC) != 0) {
+        return linux_neg_errno(13); // EACCES
+    }
 
     let map_fixed_requested = (flags & LINUX_MAP_FIXED) != 0;
     let can_try_in_place = map_fixed_requested && requested_addr != 0;
@@ -15296,6 +16027,7 @@ fn linux_sys_mmap(
             let slot_addr = state.maps[slot_idx].addr;
             let slot_len = state.maps[slot_idx].len;
             if slot_addr == requested_addr && slot_len == aligned_len {
+                linux_apply_mmap_prot(&mut state.maps[slot_idx], slot_addr, aligned_len, 0);
                 unsafe {
                     ptr::write_bytes(slot_addr as *mut u8, 0, aligned_len as usize);
                 }
@@ -15339,6 +16071,7 @@ fn linux_sys_mmap(
                 let slot = &mut state.maps[slot_idx];
                 slot.prot = prot;
                 slot.flags = flags;
+                linux_apply_mmap_prot(slot, slot_addr, aligned_len, prot);
                 return slot.addr as i64;
             }
         }
@@ -15455,7 +16188,9 @@ fn linux_sys_mmap(
         flags,
         backing_ptr: addr,
         backing_len: aligned_len,
+        exec_pages: false,
     };
+    linux_apply_mmap_prot(&mut state.maps[slot_idx], addr, aligned_len, prot);
     state.mmap_count = state.mmap_count.saturating_add(1);
     state.mmap_cursor = state.mmap_cursor.saturating_add(aligned_len).min(LINUX_MMAP_LIMIT);
     addr as i64
@@ -15468,10 +16203,15 @@ fn linux_sys_mprotect(state: &mut LinuxShimState, addr: u64, len: u64, prot: u64
     let Some(aligned_len) = linux_align_up(len, LINUX_PAGE_SIZE) else {
         return linux_neg_errno(22);
     };
+    if !crate::wx::permits((prot & LINUX_PROT_WRITE) != 0, (prot & LINUX_PROT_EXEC) != 0) {
+        return linux_neg_errno(13); // EACCES
+    }
     let Some(slot_idx) = linux_find_mmap_slot_for_range(state, addr, aligned_len) else {
         return linux_neg_errno(12); // ENOMEM
     };
-    state.maps[slot_idx].prot = prot;
+    let slot = &mut state.maps[slot_idx];
+    slot.prot = prot;
+    linux_apply_mmap_prot(slot, addr, aligned_len, prot);
     0
 }
 
@@ -15508,8 +16248,9 @@ fn linux_sys_mincore(addr: u64, len: u64, vec: u64) -> i64 {
     if pages > usize::MAX as u64 {
         return linux_neg_errno(12); // ENOMEM
     }
-    unsafe {
-        ptr::write_bytes(vec as *mut u8, 1, pages as usize);
+    let resident = alloc::vec![1u8; pages as usize];
+    if crate::uaccess::copy_to_user(vec, &resident).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
     0
 }
@@ -15572,7 +16313,7 @@ fn linux_sys_munmap(state: &mut LinuxShimState, addr: u64, len: u64) -> i64 {
         state.mmap_count -= 1;
     }
     if state.mmap_count == 0 {
-        state.mmap_cursor = LINUX_MMAP_BASE;
+        state.mmap_cursor = state.mmap_base;
     }
     0
 }
@@ -15595,17 +16336,14 @@ fn linux_sys_clock_gettime(clock_id: u64, tp: u64) -> i64 {
         let ticks = timer::ticks();
         ((ticks / 1000) as i64, ((ticks % 1000) * 1_000_000) as i64)
     };
-    unsafe {
-        let out = tp as *mut LinuxTimespec;
-        ptr::write(
-            out,
-            LinuxTimespec {
-                tv_sec: secs,
-                tv_nsec: nanos,
-            },
-        );
+    let ts = LinuxTimespec {
+        tv_sec: secs,
+        tv_nsec: nanos,
+    };
+    match crate::uaccess::write_user(tp, ts) {
+        Ok(()) => 0,
+        Err(_) => linux_neg_errno(14), // EFAULT
     }
-    0
 }
 
 fn linux_sys_clock_getres(clock_id: u64, tp: u64) -> i64 {
@@ -15616,18 +16354,15 @@ fn linux_sys_clock_getres(clock_id: u64, tp: u64) -> i64 {
         return linux_neg_errno(22); // EINVAL
     }
 
-    unsafe {
-        let out = tp as *mut LinuxTimespec;
-        ptr::write(
-            out,
-            LinuxTimespec {
-                tv_sec: 0,
-                // Shim timer granularity is effectively 1ms.
-                tv_nsec: 1_000_000,
-            },
-        );
+    let res = LinuxTimespec {
+        tv_sec: 0,
+        // Shim timer granularity is effectively 1ms.
+        tv_nsec: 1_000_000,
+    };
+    match crate::uaccess::write_user(tp, res) {
+        Ok(()) => 0,
+        Err(_) => linux_neg_errno(14), // EFAULT
     }
-    0
 }
 
 fn linux_sys_gettimeofday(tv: u64, tz: u64) -> i64 {
@@ -15636,28 +16371,22 @@ fn linux_sys_gettimeofday(tv: u64, tz: u64) -> i64 {
     let usec = unix_ms.rem_euclid(1000).saturating_mul(1000);
 
     if tv != 0 {
-        unsafe {
-            let out = tv as *mut LinuxTimeval;
-            ptr::write(
-                out,
-                LinuxTimeval {
-                    tv_sec: secs,
-                    tv_usec: usec,
-                },
-            );
+        let now = LinuxTimeval {
+            tv_sec: secs,
+            tv_usec: usec,
+        };
+        if crate::uaccess::write_user(tv, now).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
     }
 
     if tz != 0 {
-        unsafe {
-            let out = tz as *mut LinuxTimezone;
-            ptr::write(
-                out,
-                LinuxTimezone {
-                    tz_minuteswest: -timer::wall_clock_timezone_offset_minutes(),
-                    tz_dsttime: 0,
-                },
-            );
+        let zone = LinuxTimezone {
+            tz_minuteswest: -timer::wall_clock_timezone_offset_minutes(),
+            tz_dsttime: 0,
+        };
+        if crate::uaccess::write_user(tz, zone).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
     }
     0
@@ -15694,10 +16423,10 @@ fn linux_sys_getrusage(_who: u64, usage_ptr: u64) -> i64 {
         ru_nvcsw: 0,
         ru_nivcsw: 0,
     };
-    unsafe {
-        ptr::write(usage_ptr as *mut LinuxRusage, usage);
+    match crate::uaccess::write_user(usage_ptr, usage) {
+        Ok(()) => 0,
+        Err(_) => linux_neg_errno(14), // EFAULT
     }
-    0
 }
 
 fn linux_sys_sysinfo(state: &LinuxShimState, info_ptr: u64) -> i64 {
@@ -15723,10 +16452,10 @@ fn linux_sys_sysinfo(state: &LinuxShimState, info_ptr: u64) -> i64 {
         mem_unit: 1,
         _f: [0; 8],
     };
-    unsafe {
-        ptr::write(info_ptr as *mut LinuxSysinfo, info);
+    match crate::uaccess::write_user(info_ptr, info) {
+        Ok(()) => 0,
+        Err(_) => linux_neg_errno(14), // EFAULT
     }
-    0
 }
 
 fn linux_sys_times(buf_ptr: u64) -> i64 {
@@ -15738,8 +16467,8 @@ fn linux_sys_times(buf_ptr: u64) -> i64 {
             tms_cutime: 0,
             tms_cstime: 0,
         };
-        unsafe {
-            ptr::write(buf_ptr as *mut LinuxTms, tms);
+        if crate::uaccess::write_user(buf_ptr, tms).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
     }
     ticks
@@ -15752,15 +16481,12 @@ fn linux_sys_nanosleep(req: u64, rem: u64) -> i64 {
     // Cooperative stub for phase0/phase1: we acknowledge sleep requests
     // without blocking the UI thread.
     if rem != 0 {
-        unsafe {
-            let out = rem as *mut LinuxTimespec;
-            ptr::write(
-                out,
-                LinuxTimespec {
-                    tv_sec: 0,
-                    tv_nsec: 0,
-                },
-            );
+        let left = LinuxTimespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        if crate::uaccess::write_user(rem, left).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
     }
     0
@@ -15797,8 +16523,10 @@ fn linux_sys_futex(
             if cmd == LINUX_FUTEX_WAIT_BITSET && val3 == 0 {
                 return linux_neg_errno(22); // EINVAL
             }
-            let current = unsafe { ptr::read_volatile(uaddr as *const u32) as u64 };
-            if current != (val as u32 as u64) {
+            let Ok(current) = crate::uaccess::read_user::<u32>(uaddr) else {
+                return linux_neg_errno(14); // EFAULT
+            };
+            if current as u64 != (val as u32 as u64) {
                 return linux_neg_errno(11); // EAGAIN
             }
             let absolute_timeout = cmd == LINUX_FUTEX_WAIT_BITSET && (op & LINUX_FUTEX_CLOCK_REALTIME) != 0;
@@ -15859,8 +16587,10 @@ fn linux_sys_futex(
             if (uaddr2 & 0x3) != 0 {
                 return linux_neg_errno(22); // EINVAL
             }
-            let current = unsafe { ptr::read_volatile(uaddr as *const u32) as u64 };
-            if current != (val3 as u32 as u64) {
+            let Ok(current) = crate::uaccess::read_user::<u32>(uaddr) else {
+                return linux_neg_errno(14); // EFAULT
+            };
+            if current as u64 != (val3 as u32 as u64) {
                 return linux_neg_errno(11); // EAGAIN
             }
             linux_requeue_futex_waiters(state, uaddr, uaddr2, val, timeout_or_val2)
@@ -15878,8 +16608,10 @@ fn linux_sys_futex(
             if val3 != 0 {
                 return linux_neg_errno(22); // EINVAL
             }
-            let current = unsafe { ptr::read_volatile(uaddr as *const u32) as u64 };
-            if current != (val as u32 as u64) {
+            let Ok(current) = crate::uaccess::read_user::<u32>(uaddr) else {
+                return linux_neg_errno(14); // EFAULT
+            };
+            if current as u64 != (val as u32 as u64) {
                 return linux_neg_errno(11); // EAGAIN
             }
             let absolute_timeout = (op & LINUX_FUTEX_CLOCK_REALTIME) != 0;
@@ -15910,8 +16642,10 @@ fn linux_sys_futex(
             if val != 1 {
                 return linux_neg_errno(22); // EINVAL (Linux requires nr_wake=1)
             }
-            let current = unsafe { ptr::read_volatile(uaddr as *const u32) as u64 };
-            if current != (val3 as u32 as u64) {
+            let Ok(current) = crate::uaccess::read_user::<u32>(uaddr) else {
+                return linux_neg_errno(14); // EFAULT
+            };
+            if current as u64 != (val3 as u32 as u64) {
                 return linux_neg_errno(11); // EAGAIN
             }
             linux_requeue_pi_waiters(state, uaddr, uaddr2, val, timeout_or_val2)
@@ -15946,7 +16680,10 @@ fn linux_sys_futex_waitv(
     let mut wait_uaddrs = [0u64; LINUX_FUTEX_WAITV_MAX];
     let mut i = 0usize;
     while i < count {
-        let item = unsafe { ptr::read((waiters_ptr as *const LinuxFutexWaitV).add(i)) };
+        let item_ptr = waiters_ptr.saturating_add((i * core::mem::size_of::<LinuxFutexWaitV>()) as u64);
+        let Ok(item) = crate::uaccess::read_user::<LinuxFutexWaitV>(item_ptr) else {
+            return linux_neg_errno(14); // EFAULT
+        };
         if item._reserved != 0 {
             return linux_neg_errno(22); // EINVAL
         }
@@ -15963,8 +16700,10 @@ fn linux_sys_futex_waitv(
         if (item.flags & LINUX_FUTEX_32) == 0 {
             return linux_neg_errno(22); // EINVAL
         }
-        let current = unsafe { ptr::read_volatile(item.uaddr as *const u32) as u64 };
-        if current != (item.val as u32 as u64) {
+        let Ok(current) = crate::uaccess::read_user::<u32>(item.uaddr) else {
+            return linux_neg_errno(14); // EFAULT
+        };
+        if current as u64 != (item.val as u32 as u64) {
             return linux_neg_errno(11); // EAGAIN
         }
         wait_uaddrs[i] = item.uaddr;
@@ -16070,30 +16809,18 @@ fn linux_sys_setresgid(_rgid: u64, _egid: u64, _sgid: u64) -> i64 {
 }
 
 fn linux_sys_getresuid(ruid: u64, euid: u64, suid: u64) -> i64 {
-    unsafe {
-        if ruid != 0 {
-            ptr::write(ruid as *mut u32, 0);
-        }
-        if euid != 0 {
-            ptr::write(euid as *mut u32, 0);
-        }
-        if suid != 0 {
-            ptr::write(suid as *mut u32, 0);
+    for out in [ruid, euid, suid] {
+        if out != 0 && crate::uaccess::write_user(out, 0u32).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
     }
     0
 }
 
 fn linux_sys_getresgid(rgid: u64, egid: u64, sgid: u64) -> i64 {
-    unsafe {
-        if rgid != 0 {
-            ptr::write(rgid as *mut u32, 0);
-        }
-        if egid != 0 {
-            ptr::write(egid as *mut u32, 0);
-        }
-        if sgid != 0 {
-            ptr::write(sgid as *mut u32, 0);
+    for out in [rgid, egid, sgid] {
+        if out != 0 && crate::uaccess::write_user(out, 0u32).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
     }
     0
@@ -16110,9 +16837,8 @@ fn linux_sys_arch_prctl(state: &mut LinuxShimState, code: u64, addr: u64) -> i64
             if addr == 0 {
                 return linux_neg_errno(14); // EFAULT
             }
-            unsafe {
-                let out = addr as *mut u64;
-                ptr::write(out, state.fs_base);
+            if crate::uaccess::write_user(addr, state.fs_base).is_err() {
+                return linux_neg_errno(14); // EFAULT
             }
             0
         }
@@ -16123,11 +16849,8 @@ fn linux_sys_arch_prctl(state: &mut LinuxShimState, code: u64, addr: u64) -> i64
 fn linux_sys_set_tid_address(state: &mut LinuxShimState, addr: u64) -> i64 {
     state.tid_addr = addr;
     linux_sync_current_thread_to_slot(state);
-    if addr != 0 {
-        unsafe {
-            let out = addr as *mut u32;
-            ptr::write(out, state.current_tid.max(state.tid_value));
-        }
+    if addr != 0 && crate::uaccess::write_user(addr, state.current_tid.max(state.tid_value)).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
     state.current_tid.max(state.tid_value) as i64
 }
@@ -16169,6 +16892,7 @@ fn linux_sys_clone_spawn(
             parent_proc.brk_base,
             parent_proc.brk_current,
             parent_proc.brk_limit,
+            parent_proc.mmap_base,
             parent_proc.mmap_cursor,
             0,
         )
@@ -16238,15 +16962,12 @@ fn linux_sys_clone_spawn(
         state.next_pid = state.next_pid.max(child_pid);
     }
 
+    // The child already exists, so a bad pointer only loses the tid store, as on Linux.
     if (flags & LINUX_CLONE_PARENT_SETTID) != 0 && parent_tid_ptr != 0 {
-        unsafe {
-            ptr::write(parent_tid_ptr as *mut u32, child_tid);
-        }
+        let _ = crate::uaccess::write_user(parent_tid_ptr, child_tid);
     }
     if (flags & LINUX_CLONE_CHILD_SETTID) != 0 && child_tid_ptr != 0 {
-        unsafe {
-            ptr::write(child_tid_ptr as *mut u32, child_tid);
-        }
+        let _ = crate::uaccess::write_user(child_tid_ptr, child_tid);
     }
 
     if let Some(mut child_ctx) = linux_thread_context_from_privilege() {
@@ -16383,7 +17104,9 @@ fn linux_read_execve_item_vector(ptr_raw: u64, max_items: usize) -> Result<Vec<S
     let mut scratch = [0u8; LINUX_EXECVE_MAX_ITEM_LEN];
     let mut i = 0usize;
     while i < max_items {
-        let item_ptr = unsafe { ptr::read((ptr_raw as *const u64).add(i)) };
+        let Ok(item_ptr) = crate::uaccess::read_user::<u64>(ptr_raw + (i * 8) as u64) else {
+            return Err(linux_neg_errno(14)); // EFAULT
+        };
         if item_ptr == 0 {
             break;
         }
@@ -16407,6 +17130,17 @@ fn linux_close_cloexec_fds(state: &mut LinuxShimState) {
     }
 }
 
+/// (brk base, brk limit, mmap base) for a new process image. With ASLR the
+/// brk region moves down and the mmap area up by a random number of pages.
+fn linux_new_layout() -> (u64, u64, u64) {
+    let brk_base = LINUX_MMAP_BASE
+        .saturating_sub(LINUX_BRK_REGION_BYTES)
+        .saturating_sub(crate::aslr::brk_gap());
+    let brk_base_aligned = linux_align_up(brk_base, LINUX_PAGE_SIZE).unwrap_or(brk_base);
+    let brk_limit = brk_base_aligned.saturating_add(LINUX_BRK_REGION_BYTES);
+    (brk_base_aligned, brk_limit, LINUX_MMAP_BASE + crate::aslr::mmap_shift())
+}
+
 fn linux_execve_reset_process_image(state: &mut LinuxShimState, tls_tcb_addr: u64) {
     let current_pid = if state.current_pid != 0 {
         state.current_pid
@@ -16428,14 +17162,13 @@ fn linux_execve_reset_process_image(state: &mut LinuxShimState, tls_tcb_addr: u6
 
     linux_release_process_mmaps(state, current_pid);
 
-    let brk_base = LINUX_MMAP_BASE.saturating_sub(LINUX_BRK_REGION_BYTES);
-    let brk_base_aligned = linux_align_up(brk_base, LINUX_PAGE_SIZE).unwrap_or(brk_base);
-    let brk_limit = brk_base_aligned.saturating_add(LINUX_BRK_REGION_BYTES);
+    let (brk_base_aligned, brk_limit, mmap_base) = linux_new_layout();
 
     state.brk_base = brk_base_aligned;
     state.brk_current = brk_base_aligned;
     state.brk_limit = brk_limit;
-    state.mmap_cursor = LINUX_MMAP_BASE;
+    state.mmap_base = mmap_base;
+    state.mmap_cursor = mmap_base;
     state.mmap_count = 0;
 
     state.processes = [LinuxProcessSlot::empty(); LINUX_MAX_PROCESSES];
@@ -16449,7 +17182,8 @@ fn linux_execve_reset_process_image(state: &mut LinuxShimState, tls_tcb_addr: u6
         brk_base: brk_base_aligned,
         brk_current: brk_base_aligned,
         brk_limit,
-        mmap_cursor: LINUX_MMAP_BASE,
+        mmap_base,
+        mmap_cursor: mmap_base,
         mmap_count: 0,
     };
     state.process_count = 1;
@@ -17071,12 +17805,12 @@ fn linux_sys_clone3(state: &mut LinuxShimState, clone_args_ptr: u64, size: u64)
 
     let copy_len = (size as usize).min(core::mem::size_of::<LinuxCloneArgs>());
     let mut args = LinuxCloneArgs::empty();
-    unsafe {
-        ptr::copy_nonoverlapping(
-            clone_args_ptr as *const u8,
-            (&mut args as *mut LinuxCloneArgs) as *mut u8,
-            copy_len,
-        );
+    // SAFETY: LinuxCloneArgs is repr(C) plain integers; any byte pattern is a valid value.
+    let args_bytes = unsafe {
+        core::slice::from_raw_parts_mut((&mut args as *mut LinuxCloneArgs) as *mut u8, copy_len)
+    };
+    if crate::uaccess::copy_from_user(args_bytes, clone_args_ptr).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
 
     if (args.flags & LINUX_CLONE_INTO_CGROUP) != 0 && size < LINUX_CLONE_ARGS_SIZE_VER2 {
@@ -17099,7 +17833,9 @@ fn linux_sys_clone3(state: &mut LinuxShimState, clone_args_ptr: u64, size: u64)
         let mut desired = 0u32;
         let mut i = 0usize;
         while i < max_scan {
-            let candidate = unsafe { ptr::read((args.set_tid as *const u32).add(i)) };
+            let Ok(candidate) = crate::uaccess::read_user::<u32>(args.set_tid + (i * 4) as u64) else {
+                return linux_neg_errno(14); // EFAULT
+            };
             if candidate != 0 {
                 if desired == 0 {
                     desired = candidate;
@@ -17133,12 +17869,9 @@ fn linux_sys_clone3(state: &mut LinuxShimState, clone_args_ptr: u64, size: u64)
         let target_pid = linux_find_thread_slot_index(state, child_tid as u32)
             .map(|idx| state.threads[idx].process_pid)
             .unwrap_or(child_tid as u32);
-        if let Some(pidfd) = linux_create_pidfd(state, target_pid) {
-            unsafe { ptr::write(args.pidfd as *mut i32, pidfd); }
-        } else {
-            // Could not allocate fd – write -1 so caller knows.
-            unsafe { ptr::write(args.pidfd as *mut i32, -1); }
-        }
+        // Could not allocate fd – write -1 so caller knows.
+        let pidfd = linux_create_pidfd(state, target_pid).unwrap_or(-1);
+        let _ = crate::uaccess::write_user(args.pidfd, pidfd);
     }
 
     child_tid
@@ -17273,20 +18006,22 @@ fn linux_waitid_filter_from_id(idtype: u64, id: u64) -> Option<i64> {
     }
 }
 
-fn linux_waitid_write_siginfo(infop: u64, pid: u32, si_code: i32, status: i32) {
-    unsafe {
-        ptr::write_bytes(infop as *mut u8, 0, 128);
-        ptr::write(infop as *mut i32, LINUX_SIGCHLD as i32); // si_signo
-        ptr::write(infop.saturating_add(4) as *mut i32, 0); // si_errno
-        ptr::write(infop.saturating_add(8) as *mut i32, si_code); // si_code
-        ptr::write(infop.saturating_add(16) as *mut i32, pid as i32); // si_pid
-        ptr::write(infop.saturating_add(24) as *mut i32, status); // si_status
+fn linux_waitid_write_siginfo(infop: u64, pid: u32, si_code: i32, status: i32) -> i64 {
+    let mut info = [0u8; 128];
+    info[0..4].copy_from_slice(&(LINUX_SIGCHLD as i32).to_le_bytes()); // si_signo
+    info[8..12].copy_from_slice(&si_code.to_le_bytes()); // si_code
+    info[16..20].copy_from_slice(&(pid as i32).to_le_bytes()); // si_pid
+    info[24..28].copy_from_slice(&status.to_le_bytes()); // si_status
+    match crate::uaccess::copy_to_user(infop, &info) {
+        Ok(()) => 0,
+        Err(_) => linux_neg_errno(14), // EFAULT
     }
 }
 
-fn linux_waitid_write_empty_siginfo(infop: u64) {
-    unsafe {
-        ptr::write_bytes(infop as *mut u8, 0, 128);
+fn linux_waitid_write_empty_siginfo(infop: u64) -> i64 {
+    match crate::uaccess::clear_user(infop, 128) {
+        Ok(()) => 0,
+        Err(_) => linux_neg_errno(14), // EFAULT
     }
 }
 
@@ -17303,10 +18038,10 @@ fn linux_sys_wait4(
 
     if let Some(idx) = linux_find_exited_index(state, parent_pid, pid_i) {
         if let Some((child_pid, status, _kind)) = linux_take_exited_at(state, idx) {
-            if wstatus_ptr != 0 {
-                unsafe {
-                    ptr::write(wstatus_ptr as *mut i32, linux_wait_status_from_exit_code(status));
-                }
+            if wstatus_ptr != 0
+                && crate::uaccess::write_user(wstatus_ptr, linux_wait_status_from_exit_code(status)).is_err()
+            {
+                return linux_neg_errno(14); // EFAULT
             }
             return child_pid as i64;
         }
@@ -17363,14 +18098,12 @@ fn linux_sys_waitid(
                 LINUX_CHILD_EVENT_CONTINUED => (LINUX_CLD_CONTINUED, status),
                 _ => (LINUX_CLD_EXITED, status & 0xff),
             };
-            linux_waitid_write_siginfo(infop, child_pid, si_code, si_status);
-            return 0;
+            return linux_waitid_write_siginfo(infop, child_pid, si_code, si_status);
         }
     }
 
     if nohang {
-        linux_waitid_write_empty_siginfo(infop);
-        return 0;
+        return linux_waitid_write_empty_siginfo(infop);
     }
     if linux_any_active_child_exists(state, parent_pid, pid_filter) {
         return linux_neg_errno(11); // EAGAIN (cooperative non-blocking shim)
@@ -17401,9 +18134,10 @@ fn linux_sys_get_robust_list(state: &LinuxShimState, pid: u64, head_ptr: u64, le
     if !slot.active {
         return linux_neg_errno(3); // ESRCH
     }
-    unsafe {
-        ptr::write(head_ptr as *mut u64, slot.robust_list_head);
-        ptr::write(len_ptr as *mut u64, slot.robust_list_len);
+    if crate::uaccess::write_user(head_ptr, slot.robust_list_head).is_err()
+        || crate::uaccess::write_user(len_ptr, slot.robust_list_len).is_err()
+    {
+        return linux_neg_errno(14); // EFAULT
     }
     0
 }
@@ -17466,14 +18200,19 @@ fn linux_sys_rt_sigaction(state: &mut LinuxShimState, sig: u64, act: u64, oldact
         return linux_neg_errno(22); // EINVAL
     }
     let idx = sig as usize;
-    if oldact != 0 {
-        unsafe {
-            ptr::write(oldact as *mut LinuxKernelSigAction, state.sigactions[idx]);
+    let new_action = if act != 0 {
+        match crate::uaccess::read_user::<LinuxKernelSigAction>(act) {
+            Ok(action) => Some(action),
+            Err(_) => return linux_neg_errno(14), // EFAULT
         }
+    } else {
+        None
+    };
+    if oldact != 0 && crate::uaccess::write_user(oldact, state.sigactions[idx]).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
-    if act != 0 {
-        let new_action = unsafe { ptr::read(act as *const LinuxKernelSigAction) };
-        state.sigactions[idx] = new_action;
+    if let Some(action) = new_action {
+        state.sigactions[idx] = action;
     }
     0
 }
@@ -17482,15 +18221,15 @@ fn linux_sys_rt_sigprocmask(state: &mut LinuxShimState, how: u64, set: u64, olds
     if sigset_size != 0 && sigset_size < core::mem::size_of::<u64>() as u64 {
         return linux_neg_errno(22); // EINVAL
     }
-    if oldset != 0 {
-        unsafe {
-            ptr::write(oldset as *mut u64, state.signal_mask);
-        }
+    if oldset != 0 && crate::uaccess::write_user(oldset, state.signal_mask).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
     if set == 0 {
         return 0;
     }
-    let new_mask = unsafe { ptr::read(set as *const u64) };
+    let Ok(new_mask) = crate::uaccess::read_user::<u64>(set) else {
+        return linux_neg_errno(14); // EFAULT
+    };
     match how {
         LINUX_SIG_BLOCK => state.signal_mask |= new_mask,
         LINUX_SIG_UNBLOCK => state.signal_mask &= !new_mask,
@@ -17508,8 +18247,8 @@ fn linux_sys_rt_sigpending(state: &LinuxShimState, set: u64, sigset_size: u64) -
     if sigset_size != 0 && sigset_size < core::mem::size_of::<u64>() as u64 {
         return linux_neg_errno(22); // EINVAL
     }
-    unsafe {
-        ptr::write(set as *mut u64, state.pending_signals);
+    if crate::uaccess::write_user(set, state.pending_signals).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
     0
 }
@@ -17519,7 +18258,9 @@ fn linux_sys_rt_sigsuspend(state: &mut LinuxShimState, set: u64, sigset_size: u6
         if sigset_size != 0 && sigset_size < core::mem::size_of::<u64>() as u64 {
             return linux_neg_errno(22); // EINVAL
         }
-        let new_mask = unsafe { ptr::read(set as *const u64) };
+        let Ok(new_mask) = crate::uaccess::read_user::<u64>(set) else {
+            return linux_neg_errno(14); // EFAULT
+        };
         state.signal_mask = new_mask;
         linux_sync_current_thread_to_slot(state);
     }
@@ -17528,16 +18269,14 @@ fn linux_sys_rt_sigsuspend(state: &mut LinuxShimState, set: u64, sigset_size: u6
 
 fn linux_sys_sigaltstack(_uss: u64, uoss: u64) -> i64 {
     if uoss != 0 {
-        unsafe {
-            ptr::write(
-                uoss as *mut LinuxStackT,
-                LinuxStackT {
-                    sp: 0,
-                    flags: LINUX_SS_DISABLE,
-                    _pad: 0,
-                    size: 0,
-                },
-            );
+        let disabled = LinuxStackT {
+            sp: 0,
+            flags: LINUX_SS_DISABLE,
+            _pad: 0,
+            size: 0,
+        };
+        if crate::uaccess::write_user(uoss, disabled).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
     }
     0
@@ -17565,15 +18304,12 @@ fn linux_sys_setrlimit(resource: u64, new_limit: u64) -> i64 {
 
 fn linux_sys_prlimit64(_pid: u64, _resource: u64, _new_limit: u64, old_limit: u64) -> i64 {
     if old_limit != 0 {
-        unsafe {
-            let out = old_limit as *mut LinuxRlimit;
-            ptr::write(
-                out,
-                LinuxRlimit {
-                    rlim_cur: u64::MAX,
-                    rlim_max: u64::MAX,
-                },
-            );
+        let limit = LinuxRlimit {
+            rlim_cur: u64::MAX,
+            rlim_max: u64::MAX,
+        };
+        if crate::uaccess::write_user(old_limit, limit).is_err() {
+            return linux_neg_errno(14); // EFAULT
         }
     }
     0
@@ -17618,6 +18354,9 @@ fn linux_sys_mremap(
     }
 
     if new_len < old_len {
+        if state.maps[slot_idx].exec_pages {
+            crate::wx::set_guest_exec(old_addr + new_len, old_len - new_len, false);
+        }
         state.maps[slot_idx].len = new_len;
         state.maps[slot_idx].backing_len = new_len;
         return old_addr as i64;
@@ -17654,7 +18393,9 @@ fn linux_sys_mremap(
         flags: old_flags,
         backing_ptr: new_ptr as u64,
         backing_len: new_len,
+        exec_pages: false,
     };
+    linux_apply_mmap_prot(&mut state.maps[slot_idx], new_ptr as u64, new_len, old_prot);
     state.mmap_cursor = state.mmap_cursor.saturating_add(new_len).min(LINUX_MMAP_LIMIT);
     new_ptr as u64 as i64
 }
@@ -17699,22 +18440,15 @@ fn linux_sys_shmctl(_state: &mut LinuxShimState, _shmid: u64, cmd: u64, _buf: u6
     0
 }
 
-fn linux_sys_getrandom(state: &LinuxShimState, buf: u64, len: u64, _flags: u64) -> i64 {
+fn linux_sys_getrandom(buf: u64, len: u64, _flags: u64) -> i64 {
     if buf == 0 {
         return linux_neg_errno(14); // EFAULT
     }
     let copy_len = (len as usize).min(LINUX_GETRANDOM_MAX);
-    let mut seed = timer::ticks() ^ state.session_id.rotate_left(17);
-    unsafe {
-        let dst = buf as *mut u8;
-        let mut i = 0usize;
-        while i < copy_len {
-            seed ^= seed << 13;
-            seed ^= seed >> 7;
-            seed ^= seed << 17;
-            ptr::write(dst.add(i), (seed & 0xFF) as u8);
-            i += 1;
-        }
+    let mut bytes = [0u8; LINUX_GETRANDOM_MAX];
+    crate::random::fill(&mut bytes[..copy_len]);
+    if crate::uaccess::copy_to_user(buf, &bytes[..copy_len]).is_err() {
+        return linux_neg_errno(14); // EFAULT
     }
     copy_len as i64
 }
@@ -17723,25 +18457,21 @@ fn linux_sys_uname(buf: u64) -> i64 {
     if buf == 0 {
         return linux_neg_errno(14); // EFAULT
     }
-    unsafe {
-        let dst = buf as *mut u8;
-        ptr::write_bytes(dst, 0, LINUX_UTS_FIELD_LEN * 6);
-        let mut field = 0usize;
-        while field < 6 {
-            let field_ptr = dst.add(field * LINUX_UTS_FIELD_LEN);
-            let field_slice = core::slice::from_raw_parts_mut(field_ptr, LINUX_UTS_FIELD_LEN);
-            match field {
-                0 => linux_fill_ascii_field(field_slice, "Linux"),
-                1 => linux_fill_ascii_field(field_slice, "goos"),
-                2 => linux_fill_ascii_field(field_slice, "6.6.0"),
-                3 => linux_fill_ascii_field(field_slice, "#1 Go OS"),
-                4 => linux_fill_ascii_field(field_slice, "x86_64"),
-                _ => linux_fill_ascii_field(field_slice, ""),
-            }
-            field += 1;
+    let mut uts = [0u8; LINUX_UTS_FIELD_LEN * 6];
+    for (field, field_slice) in uts.chunks_exact_mut(LINUX_UTS_FIELD_LEN).enumerate() {
+        match field {
+            0 => linux_fill_ascii_field(field_slice, "Linux"),
+            1 => linux_fill_ascii_field(field_slice, "goos"),
+            2 => linux_fill_ascii_field(field_slice, "6.6.0"),
+            3 => linux_fill_ascii_field(field_slice, "#1 Go OS"),
+            4 => linux_fill_ascii_field(field_slice, "x86_64"),
+            _ => linux_fill_ascii_field(field_slice, ""),
         }
     }
-    0
+    match crate::uaccess::copy_to_user(buf, &uts) {
+        Ok(()) => 0,
+        Err(_) => linux_neg_errno(14), // EFAULT
+    }
 }
 
 const SYSCALL_TABLE: [SysHandler; SYS_COUNT] = [
@@ -17755,6 +18485,23 @@ const SYSCALL_TABLE: [SysHandler; SYS_COUNT] = [
     handle_priv_status,
     handle_priv_next,
     handle_priv_unsafe_test,
+    handle_http_open,
+    handle_http_set_header,
+    handle_http_send,
+    handle_http_headers,
+    handle_http_read,
+    handle_http_close,
+    handle_config_get,
+    handle_config_set,
+    handle_config_watch,
+    handle_http_set_option,
+    handle_get_random,
+    handle_fs_watch,
+    handle_fs_read_events,
+    handle_fs_unwatch,
+    handle_audio_capture_open,
+    handle_audio_capture_read,
+    handle_audio_capture_close,
 ];
 
 static mut SYSCALL_COUNTS: [u64; SYS_COUNT] = [0; SYS_COUNT];
@@ -17801,13 +18548,16 @@ fn map_plan_to_cr3(cr3: Option<u64>) {
                 offset += LINUX_PAGE_SIZE;
             }
         };
-        map_buf(&plan.main_image.image);
+        map_buf(plan.main_image.image.as_slice());
         map_buf(&plan.main_image.phdr_blob);
         map_buf(&plan.main_image.tls_block);
-        map_buf(&plan.interp_image.image);
+        map_buf(plan.interp_image.image.as_slice());
         map_buf(&plan.interp_image.phdr_blob);
         map_buf(&plan.interp_image.tls_block);
         map_buf(&plan.stack_image);
+        // map_page leaves every page writable and non-executable again.
+        plan.main_image.protect_segments();
+        plan.interp_image.protect_segments();
     }
 }
 
@@ -17829,9 +18579,7 @@ pub fn linux_shim_begin(main_entry: u64, interp_entry: u64, stack_ptr: u64, tls_
             session_id = 1;
         }
         LINUX_SHIM_NEXT_SESSION_ID = session_id.saturating_add(1);
-        let brk_base = LINUX_MMAP_BASE.saturating_sub(LINUX_BRK_REGION_BYTES);
-        let brk_base_aligned = linux_align_up(brk_base, LINUX_PAGE_SIZE).unwrap_or(brk_base);
-        let brk_limit = brk_base_aligned.saturating_add(LINUX_BRK_REGION_BYTES);
+        let (brk_base_aligned, brk_limit, mmap_base) = linux_new_layout();
         let mut pid_value = (1000u64.saturating_add(session_id) & 0xFFFF_FFFF) as u32;
         if pid_value == 0 {
             pid_value = 1;
@@ -17853,7 +18601,8 @@ pub fn linux_shim_begin(main_entry: u64, interp_entry: u64, stack_ptr: u64, tls_
         state.brk_base = brk_base_aligned;
         state.brk_current = brk_base_aligned;
         state.brk_limit = brk_limit;
-        state.mmap_cursor = LINUX_MMAP_BASE;
+        state.mmap_base = mmap_base;
+        state.mmap_cursor = mmap_base;
         state.tid_value = tid_value;
         state.current_tid = tid_value;
         state.current_pid = pid_value;
@@ -17873,7 +18622,8 @@ pub fn linux_shim_begin(main_entry: u64, interp_entry: u64, stack_ptr: u64, tls_
             brk_base: brk_base_aligned,
             brk_current: brk_base_aligned,
             brk_limit,
-            mmap_cursor: LINUX_MMAP_BASE,
+            mmap_base,
+            mmap_cursor: mmap_base,
             mmap_count: 0,
         };
         state.threads[0] = LinuxThreadSlot {
@@ -18057,6 +18807,268 @@ pub fn linux_shim_status() -> LinuxShimStatus {
     }
 }
 
+/// User memory of the active plan that a checkpoint saves: the loaded
+/// images, their program headers and TLS blocks, and the initial stack.
+unsafe fn linux_plan_regions() -> Vec<(crate::checkpoint::RegionKind, u64, *mut u8, usize)> {
+    use crate::checkpoint::RegionKind;
+    let mut out = Vec::new();
+    if LINUX_SHIM_ACTIVE_PLAN.is_null() {
+        return out;
+    }
+    let plan = &mut *LINUX_SHIM_ACTIVE_PLAN;
+    let mut push = |kind: RegionKind, prot: u64, buf: &mut [u8]| {
+        if !buf.is_empty() {
+            out.push((kind, prot, buf.as_mut_ptr(), buf.len()));
+        }
+    };
+    let code = LINUX_PROT_READ | LINUX_PROT_EXEC;
+    let data = LINUX_PROT_READ | LINUX_PROT_WRITE;
+    push(RegionKind::Image, code, plan.main_image.image.as_mut_slice());
+    push(RegionKind::Image, data, plan.main_image.phdr_blob.as_mut_slice());
+    push(RegionKind::Image, data, plan.main_image.tls_block.as_mut_slice());
+    push(RegionKind::Image, code, plan.interp_image.image.as_mut_slice());
+    push(RegionKind::Image, data, plan.interp_image.phdr_blob.as_mut_slice());
+    push(RegionKind::Image, data, plan.interp_image.tls_block.as_mut_slice());
+    push(RegionKind::Stack, data, plan.stack_image.as_mut_slice());
+    out
+}
+
+/// Bytes of the brk heap that are mapped in the process page tables.
+fn linux_brk_mapped_len(brk_base: u64, brk_current: u64) -> u64 {
+    linux_align_up(brk_current, LINUX_PAGE_SIZE)
+        .unwrap_or(brk_current)
+        .saturating_sub(brk_base)
+}
+
+/// `len` bytes of process memory at `addr`, for a checkpoint.
+fn linux_checkpoint_read(addr: u64, len: usize) -> Result<Vec<u8>, &'static str> {
+    let mut bytes = alloc::vec![0u8; len];
+    crate::uaccess::copy_from_user(&mut bytes, addr).map_err(|_| "no se pudo leer la memoria del proceso")?;
+    Ok(bytes)
+}
+
+/// Capture the current Linux process between two run slices. Only
+/// single-threaded processes are supported: other threads' stacks and
+/// futex waits are not part of the snapshot.
+pub fn linux_shim_checkpoint() -> Result<crate::checkpoint::Snapshot, &'static str> {
+    use crate::checkpoint::{Descriptor, Region, RegionKind, Snapshot};
+    unsafe {
+        let state = &mut LINUX_SHIM;
+        if !state.active || LINUX_SHIM_ACTIVE_PLAN.is_null() {
+            return Err("no hay un proceso Linux activo");
+        }
+        if state.thread_count != 1 || state.process_count != 1 {
+            return Err("solo se guardan procesos de un hilo");
+        }
+        if state.exec_transition_pending {
+            return Err("el proceso esta cambiando de imagen (execve)");
+        }
+        if privilege::linux_real_context_valid() {
+            linux_capture_current_thread_context(state, None);
+        }
+        let Some(thread_idx) = linux_find_current_thread_slot_index(state) else {
+            return Err("hilo actual no encontrado");
+        };
+        let ctx = state.thread_contexts[thread_idx];
+        if !ctx.valid {
+            return Err("el proceso aun no ha corrido");
+        }
+        let Some(proc_idx) = linux_find_process_slot_index(state, state.current_pid) else {
+            return Err("proceso actual no encontrado");
+        };
+        let process = state.processes[proc_idx];
+
+        let mut regions = Vec::new();
+        for (kind, prot, ptr, len) in linux_plan_regions() {
+            regions.push(Region {
+                kind,
+                addr: ptr as u64,
+                prot,
+                bytes: linux_checkpoint_read(ptr as u64, len)?,
+            });
+        }
+        for map in state.maps.iter() {
+            if !map.active || map.process_pid != process.pid || map.backing_ptr == 0 {
+                continue;
+            }
+            regions.push(Region {
+                kind: RegionKind::Mmap,
+                addr: map.addr,
+                prot: map.prot,
+                bytes: linux_checkpoint_read(map.backing_ptr, map.backing_len as usize)?,
+            });
+        }
+        let brk_len = linux_brk_mapped_len(process.brk_base, process.brk_current);
+        if brk_len > 0 {
+            // The brk pages only exist in the process page tables.
+            crate::paging::switch_to_process_cr3(process.cr3);
+            let bytes = linux_checkpoint_read(process.brk_base, brk_len as usize);
+            crate::paging::switch_to_process_cr3(None);
+            let bytes = bytes?;
+            regions.push(Region {
+                kind: RegionKind::Brk,
+                addr: process.brk_base,
+                prot: LINUX_PROT_READ | LINUX_PROT_WRITE,
+                bytes,
+            });
+        }
+
+        let mut descriptors = Vec::new();
+        for open in state.open_files.iter() {
+            if !open.active {
+                continue;
+            }
+            let mut path = String::new();
+            if open.kind == LINUX_OPEN_KIND_RUNTIME && open.object_index < LINUX_MAX_RUNTIME_FILES {
+                let file = &state.runtime_files[open.object_index];
+                let len = (file.path_len as usize).min(LINUX_PATH_MAX);
+                path.push_str(core::str::from_utf8(&file.path[..len]).unwrap_or(""));
+            }
+            descriptors.push(Descriptor {
+                fd: open.fd,
+                kind: open.kind,
+                cursor: open.cursor,
+                flags: open.flags,
+                path,
+            });
+        }
+
+        Ok(Snapshot {
+            session: state.session_id,
+            pid: process.pid,
+            tid: state.current_tid,
+            tick: timer::ticks(),
+            regs: [
+                ctx.rax, ctx.rcx, ctx.rbx, ctx.rbp, ctx.r12, ctx.r13, ctx.r14, ctx.r15, ctx.rdi, ctx.rsi, ctx.rdx,
+                ctx.r10, ctx.r11, ctx.r8, ctx.r9, ctx.rsp, ctx.rip, ctx.rflags,
+            ],
+            fs_base: state.threads[thread_idx].fs_base,
+            signal_mask: state.signal_mask,
+            brk_base: process.brk_base,
+            brk_current: process.brk_current,
+            regions,
+            descriptors,
+        })
+    }
+}
+
+/// Rewind the current Linux process to `snapshot`. Every saved region must
+/// still be mapped at the same address with at least the saved length;
+/// nothing is written otherwise. Returns the pages written and how many
+/// saved descriptors are no longer open.
+pub fn linux_shim_restore(snapshot: &crate::checkpoint::Snapshot) -> Result<(usize, usize), &'static str> {
+    use crate::checkpoint::RegionKind;
+    unsafe {
+        let state = &mut LINUX_SHIM;
+        if !state.active || LINUX_SHIM_ACTIVE_PLAN.is_null() {
+            return Err("no hay un proceso Linux activo");
+        }
+        if snapshot.session != state.session_id || snapshot.pid != state.current_pid {
+            // User memory lives at the addresses of kernel heap buffers, which
+            // change with every launch and every boot.
+            return Err("el checkpoint es de otra sesion; solo se restaura en la sesion que lo guardo");
+        }
+        if state.thread_count != 1 || state.process_count != 1 || state.current_tid != snapshot.tid {
+            return Err("solo se restauran procesos de un hilo");
+        }
+        let Some(thread_idx) = linux_find_current_thread_slot_index(state) else {
+            return Err("hilo actual no encontrado");
+        };
+        let Some(proc_idx) = linux_find_process_slot_index(state, state.current_pid) else {
+            return Err("proceso actual no encontrado");
+        };
+        let process = state.processes[proc_idx];
+        let plan_regions = linux_plan_regions();
+        let brk_mapped = linux_brk_mapped_len(process.brk_base, process.brk_current);
+
+        let mut targets: Vec<(*mut u8, bool)> = Vec::new();
+        for region in snapshot.regions.iter() {
+            let len = region.bytes.len() as u64;
+            let target = match region.kind {
+                RegionKind::Image | RegionKind::Stack => plan_regions
+                    .iter()
+                    .find(|(kind, _, ptr, plan_len)| {
+                        *kind == region.kind && *ptr as u64 == region.addr && *plan_len as u64 == len
+                    })
+                    .map(|(_, _, ptr, _)| (*ptr, false)),
+                RegionKind::Mmap => state
+                    .maps
+                    .iter()
+                    .find(|map| {
+                        map.active && map.process_pid == process.pid && map.addr == region.addr && map.backing_len >= len
+                    })
+                    .map(|map| (map.backing_ptr as *mut u8, false)),
+                RegionKind::Brk => (region.addr == process.brk_base && brk_mapped >= len)
+                    .then_some((process.brk_base as *mut u8, true)),
+            };
+            let Some(target) = target else {
+                return Err("la memoria del proceso ya no coincide con el checkpoint");
+            };
+            targets.push(target);
+        }
+
+        let mut pages = 0usize;
+        for (region, (ptr, in_process)) in snapshot.regions.iter().zip(targets.iter()) {
+            if *in_process {
+                crate::paging::switch_to_process_cr3(process.cr3);
+            }
+            let written = crate::checkpoint::copy_changed_pages(*ptr as u64, region.bytes.as_slice());
+            if *in_process {
+                crate::paging::switch_to_process_cr3(None);
+            }
+            pages += written.map_err(|_| "no se pudo escribir la memoria del proceso")?;
+        }
+
+        let r = &snapshot.regs;
+        let ctx = LinuxThreadContext {
+            valid: true,
+            rax: r[0],
+            rcx: r[1],
+            rbx: r[2],
+            rbp: r[3],
+            r12: r[4],
+            r13: r[5],
+            r14: r[6],
+            r15: r[7],
+            rdi: r[8],
+            rsi: r[9],
+            rdx: r[10],
+            r10: r[11],
+            r11: r[12],
+            r8: r[13],
+            r9: r[14],
+            rsp: r[15],
+            rip: r[16],
+            rflags: r[17],
+        };
+        state.thread_contexts[thread_idx] = ctx;
+        state.threads[thread_idx].fs_base = snapshot.fs_base;
+        state.threads[thread_idx].signal_mask = snapshot.signal_mask;
+        state.fs_base = snapshot.fs_base;
+        state.signal_mask = snapshot.signal_mask;
+        // Pages above the saved break stay mapped; brk reuses them.
+        state.processes[proc_idx].brk_current = snapshot.brk_current;
+        state.brk_current = snapshot.brk_current;
+        linux_thread_context_apply_to_privilege(&ctx, snapshot.fs_base);
+
+        let mut skipped = 0usize;
+        for saved in snapshot.descriptors.iter() {
+            match state
+                .open_files
+                .iter_mut()
+                .find(|open| open.active && open.fd == saved.fd && open.kind == saved.kind)
+            {
+                Some(open) => {
+                    open.cursor = saved.cursor;
+                    open.flags = saved.flags;
+                }
+                None => skipped += 1,
+            }
+        }
+        Ok((pages, skipped))
+    }
+}
+
 pub fn linux_x11_socket_status() -> LinuxX11SocketStatus {
     unsafe {
         let mut status = LinuxX11SocketStatus {
@@ -18098,6 +19110,38 @@ pub fn linux_shim_active() -> bool {
     unsafe { LINUX_SHIM.active }
 }
 
+/// What the current Linux process has mapped: its mmap slots, the heap up to
+/// the break and the staged images and stack. Guest pointers are checked
+/// against these while one of its syscalls runs.
+pub fn linux_guest_regions() -> crate::uaccess::GuestRegions {
+    let mut regions = crate::uaccess::GuestRegions::new();
+    unsafe {
+        let current_pid = LINUX_SHIM.current_pid;
+        for slot in LINUX_SHIM.maps.iter() {
+            if slot.active && (current_pid == 0 || slot.process_pid == current_pid) {
+                regions.push(slot.addr, slot.len);
+            }
+        }
+        let brk_end = linux_align_up(LINUX_SHIM.brk_current, LINUX_PAGE_SIZE).unwrap_or(LINUX_SHIM.brk_current);
+        regions.push(LINUX_SHIM.brk_base, brk_end.saturating_sub(LINUX_SHIM.brk_base));
+        if !LINUX_SHIM_ACTIVE_PLAN.is_null() {
+            let plan = &*LINUX_SHIM_ACTIVE_PLAN;
+            for buf in [
+                plan.main_image.image.as_slice(),
+                plan.main_image.phdr_blob.as_slice(),
+                plan.main_image.tls_block.as_slice(),
+                plan.interp_image.image.as_slice(),
+                plan.interp_image.phdr_blob.as_slice(),
+                plan.interp_image.tls_block.as_slice(),
+                plan.stack_image.as_slice(),
+            ] {
+                regions.push(buf.as_ptr() as u64, buf.len() as u64);
+            }
+        }
+    }
+    regions
+}
+
 pub fn linux_shim_set_compat_root(path: &str) -> bool {
     let requested = path.trim();
     let source = if requested.is_empty() {
@@ -18425,6 +19469,11 @@ fn linux_sysent_getcwd(_: &mut LinuxShimState, a0: u64, a1: u64, _: u64, _: u64,
     linux_sys_getcwd(a0, a1)
 }
 
+#[inline]
+fn linux_sysent_chdir(state: &mut LinuxShimState, a0: u64, _: u64, _: u64, _: u64, _: u64, _: u64) -> i64 {
+    linux_sys_chdir(state, a0)
+}
+
 #[inline]
 fn linux_sysent_readlink(state: &mut LinuxShimState, a0: u64, a1: u64, a2: u64, _: u64, _: u64, _: u64) -> i64 {
     linux_sys_readlink(state, a0, a1, a2)
@@ -18556,8 +19605,8 @@ fn linux_sysent_getcpu(_: &mut LinuxShimState, a0: u64, a1: u64, a2: u64, _: u64
 }
 
 #[inline]
-fn linux_sysent_getrandom(state: &mut LinuxShimState, a0: u64, a1: u64, a2: u64, _: u64, _: u64, _: u64) -> i64 {
-    linux_sys_getrandom(state, a0, a1, a2)
+fn linux_sysent_getrandom(_: &mut LinuxShimState, a0: u64, a1: u64, a2: u64, _: u64, _: u64, _: u64) -> i64 {
+    linux_sys_getrandom(a0, a1, a2)
 }
 
 #[inline]
@@ -18634,6 +19683,7 @@ static LINUX_SYSENT_TABLE: &[LinuxSysentEntry] = &[
     LinuxSysentEntry { sysno: LINUX_SYS_SETGID, handler: linux_sysent_setgid },
     LinuxSysentEntry { sysno: LINUX_SYS_GETPPID, handler: linux_sysent_getppid },
     LinuxSysentEntry { sysno: LINUX_SYS_GETCWD, handler: linux_sysent_getcwd },
+    LinuxSysentEntry { sysno: LINUX_SYS_CHDIR, handler: linux_sysent_chdir },
     LinuxSysentEntry { sysno: LINUX_SYS_READLINK, handler: linux_sysent_readlink },
     LinuxSysentEntry { sysno: LINUX_SYS_GETTIMEOFDAY, handler: linux_sysent_gettimeofday },
     LinuxSysentEntry { sysno: LINUX_SYS_GETRLIMIT, handler: linux_sysent_getrlimit },
@@ -18701,6 +19751,20 @@ fn linux_sysent_abi_dispatchable(sysno: u64) -> bool {
     }
 }
 
+fn redux_call_id(sysno: u64) -> Option<usize> {
+    let id = usize::try_from(sysno.checked_sub(REDUX_CALL_BASE)?).ok()?;
+    REDUX_CALLS.contains(&id).then_some(id)
+}
+
+/// Native call from a Linux ELF. Errors keep their `SYS_ERR_*` bit pattern,
+/// which reads as -2..=-11 in RAX.
+fn redux_call(id: usize, a0: u64, a1: u64, a2: u64, a3: u64) -> i64 {
+    unsafe {
+        SYSCALL_COUNTS[id] = SYSCALL_COUNTS[id].saturating_add(1);
+    }
+    SYSCALL_TABLE[id](LINUX_ELF_THREAD, a0, a1, a2, a3) as i64
+}
+
 pub fn linux_shim_invoke(sysno: u64, a0: u64, a1: u64, a2: u64, a3: u64, a4: u64, a5: u64) -> i64 {
     unsafe {
         let state = &mut LINUX_SHIM;
@@ -18730,7 +19794,9 @@ pub fn linux_shim_invoke(sysno: u64, a0: u64, a1: u64, a2: u64, a3: u64, a4: u64
         }
 
         let abi_dispatchable = linux_sysent_abi_dispatchable(sysno);
-        let result = if LINUX_SYSENT_STRICT_ABI_BOUNDS && !abi_dispatchable {
+        let result = if let Some(id) = redux_call_id(sysno) {
+            redux_call(id, a0, a1, a2, a3)
+        } else if LINUX_SYSENT_STRICT_ABI_BOUNDS && !abi_dispatchable {
             linux_neg_errno(38) // ENOSYS (outside ABI range or reserved nosys slot)
         } else if let Some(sysent_result) = linux_try_dispatch_sysent(state, sysno, a0, a1, a2, a3, a4, a5) {
             sysent_result
@@ -18800,6 +19866,7 @@ pub fn linux_shim_invoke(sysno: u64, a0: u64, a1: u64, a2: u64, a3: u64, a4: u64
             LINUX_SYS_FACCESSAT => linux_sys_faccessat(state, a0, a1, a2, a3),
             LINUX_SYS_FACCESSAT2 => linux_sys_faccessat2(state, a0, a1, a2, a3),
             LINUX_SYS_GETCWD => linux_sys_getcwd(a0, a1),
+            LINUX_SYS_CHDIR => linux_sys_chdir(state, a0),
             LINUX_SYS_READLINK => linux_sys_readlink(state, a0, a1, a2),
             LINUX_SYS_READLINKAT => linux_sys_readlinkat(state, a0, a1, a2, a3),
             LINUX_SYS_GETTIMEOFDAY => linux_sys_gettimeofday(a0, a1),
@@ -18851,7 +19918,7 @@ pub fn linux_shim_invoke(sysno: u64, a0: u64, a1: u64, a2: u64, a3: u64, a4: u64
             LINUX_SYS_SETRLIMIT => linux_sys_setrlimit(a0, a1),
             LINUX_SYS_PRLIMIT64 => linux_sys_prlimit64(a0, a1, a2, a3),
             LINUX_SYS_GETCPU => linux_sys_getcpu(a0, a1, a2),
-            LINUX_SYS_GETRANDOM => linux_sys_getrandom(state, a0, a1, a2),
+            LINUX_SYS_GETRANDOM => linux_sys_getrandom(a0, a1, a2),
             LINUX_SYS_EPOLL_WAIT => linux_sys_epoll_wait(state, a0, a1, a2, a3 as i64),
             LINUX_SYS_EPOLL_PWAIT => linux_sys_epoll_pwait(state, a0, a1, a2, a3 as i64, a4, a5),
             LINUX_SYS_EPOLL_PWAIT2 => linux_sys_epoll_pwait2(state, a0, a1, a2, a3, a4, a5),
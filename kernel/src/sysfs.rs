@@ -0,0 +1,189 @@
+//! `/sys`: read-only view of the device model.
+//!
+//! `/sys/devices` mirrors the tree; each device is a directory holding its
+//! children and one file per attribute, plus `class` and `driver` when set.
+//! `/sys/class/<clase>` and `/sys/bus/pci/{devices,drivers}` list links into
+//! `/sys/devices`, and paths through them resolve like the device itself.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::device;
+
+pub const SYS_MOUNT: &str = "/sys";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+    /// Target below `/sys` for class and bus links.
+    pub link: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Node {
+    Dir(Vec<Entry>),
+    File(String),
+}
+
+fn dir(name: &str) -> Entry {
+    Entry {
+        name: String::from(name),
+        is_dir: true,
+        link: None,
+    }
+}
+
+fn link(name: &str, target: String) -> Entry {
+    Entry {
+        name: String::from(name),
+        is_dir: true,
+        link: Some(target),
+    }
+}
+
+fn device_link(id: device::DeviceId) -> Option<Entry> {
+    let info = device::info(id)?;
+    Some(link(info.name.as_str(), alloc::format!("devices/{}", info.path)))
+}
+
+/// Directory of a device, or one of its attribute files.
+fn device_node(id: device::DeviceId, rest: &[&str]) -> Option<Node> {
+    let mut id = id;
+    for (index, part) in rest.iter().enumerate() {
+        let child = device::children(Some(id))
+            .into_iter()
+            .find(|c| device::info(*c).is_some_and(|i| i.name == *part));
+        match child {
+            Some(child) => id = child,
+            None if index + 1 == rest.len() => {
+                let info = device::info(id)?;
+                let value = match *part {
+                    "class" => info.class.map(String::from),
+                    "driver" => info.driver.map(String::from),
+                    _ => info.attrs.into_iter().find(|(k, _)| k == part).map(|(_, v)| v),
+                };
+                return value.map(Node::File);
+            }
+            None => return None,
+        }
+    }
+    let info = device::info(id)?;
+    let mut entries: Vec<Entry> = device::children(Some(id))
+        .into_iter()
+        .filter_map(device::info)
+        .map(|child| dir(child.name.as_str()))
+        .collect();
+    let mut file = |name: &str| {
+        entries.push(Entry {
+            name: String::from(name),
+            is_dir: false,
+            link: None,
+        })
+    };
+    if info.class.is_some() {
+        file("class");
+    }
+    if info.driver.is_some() {
+        file("driver");
+    }
+    for (key, _) in info.attrs.iter() {
+        file(key);
+    }
+    Some(Node::Dir(entries))
+}
+
+fn pci_functions() -> Vec<device::DeviceId> {
+    device::lookup(device::PCI_ROOT)
+        .map(|root| device::children(Some(root)))
+        .unwrap_or_default()
+}
+
+/// Resolve a path below `/sys`.
+pub fn resolve(path: &str) -> Option<Node> {
+    let rel = path.trim().strip_prefix(SYS_MOUNT)?;
+    let parts: Vec<&str> = rel.split('/').filter(|p| !p.is_empty() && *p != ".").collect();
+    match parts.as_slice() {
+        [] => Some(Node::Dir(alloc::vec![dir("bus"), dir("class"), dir("devices")])),
+        ["devices"] => Some(Node::Dir(
+            device::children(None)
+                .into_iter()
+                .filter_map(device::info)
+                .map(|i| dir(i.name.as_str()))
+                .collect(),
+        )),
+        ["devices", top, rest @ ..] => device_node(device::lookup(top)?, rest),
+        ["class"] => Some(Node::Dir(device::classes().into_iter().map(dir).collect())),
+        ["class", class] => Some(Node::Dir(
+            device::class_members(class)
+                .into_iter()
+                .filter_map(device_link)
+                .collect(),
+        )),
+        ["class", class, name, rest @ ..] => {
+            let id = device::class_members(class)
+                .into_iter()
+                .find(|id| device::info(*id).is_some_and(|i| i.name == *name))?;
+            device_node(id, rest)
+        }
+        ["bus"] => Some(Node::Dir(alloc::vec![dir("pci")])),
+        ["bus", "pci"] => Some(Node::Dir(alloc::vec![dir("devices"), dir("drivers")])),
+        ["bus", "pci", "devices"] => Some(Node::Dir(pci_functions().into_iter().filter_map(device_link).collect())),
+        ["bus", "pci", "devices", name, rest @ ..] => device_node(
+            device::lookup(alloc::format!("{}/{}", device::PCI_ROOT, name).as_str())?,
+            rest,
+        ),
+        ["bus", "pci", "drivers"] => {
+            let mut names: Vec<&'static str> = Vec::new();
+            for driver in pci_functions().into_iter().filter_map(|id| device::info(id)?.driver) {
+                if !names.contains(&driver) {
+                    names.push(driver);
+                }
+            }
+            Some(Node::Dir(names.into_iter().map(dir).collect()))
+        }
+        ["bus", "pci", "drivers", driver] => Some(Node::Dir(
+            pci_functions()
+                .into_iter()
+                .filter(|id| device::info(*id).is_some_and(|i| i.driver == Some(*driver)))
+                .filter_map(device_link)
+                .collect(),
+        )),
+        _ => None,
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "sysfs";
+
+    fn resolves_devices_and_classes() {
+        fn check() -> crate::selftest::TestResult {
+            crate::selftest::ensure_eq(
+                resolve("/sys/devices/sysfs-test/disk0/size"),
+                Some(Node::File(String::from("512"))),
+                "atributo",
+            )?;
+            crate::selftest::ensure_eq(
+                resolve("/sys/class/sysfs-test/disk0/driver"),
+                Some(Node::File(String::from("prueba"))),
+                "por clase",
+            )?;
+            let Some(Node::Dir(entries)) = resolve("/sys/class/sysfs-test") else {
+                return Err(String::from("clase sin directorio"));
+            };
+            crate::selftest::ensure_eq(
+                entries.first().and_then(|e| e.link.as_deref()),
+                Some("devices/sysfs-test/disk0"),
+                "enlace",
+            )?;
+            crate::selftest::ensure(resolve("/sys/devices/sysfs-test/nada").is_none(), "inexistente")
+        }
+
+        let top = device::add(None, "sysfs-test", None, None);
+        let child = device::add(Some(top), "disk0", Some("sysfs-test"), Some("prueba"));
+        device::set_attr(child, "size", "512");
+        let result = check();
+        device::remove(top);
+        result
+    }
+}
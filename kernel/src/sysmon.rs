@@ -0,0 +1,334 @@
+//! System monitor: periodic samples of CPU, heap and network use plus a
+//! per-process table, for the Task Manager window.
+//!
+//! Sampling reads counters that are already kept elsewhere (scheduler run
+//! counts, the perf frame breakdown, heap and smoltcp byte totals), so it
+//! costs a few locks every `SAMPLE_INTERVAL_US` and nothing in between.
+//! Process CPU% is the share of scheduler ticks its threads were
+//! dispatched in; the total is how busy the desktop loop was. Row 0 is the
+//! kernel itself (pid 0, ring 0): the desktop loop, the heap and all traffic.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::process::RingLevel;
+use crate::spinlock::SpinLock;
+
+pub const SAMPLE_INTERVAL_US: u64 = 500_000;
+/// Samples kept for the graphs: 30 seconds.
+pub const HISTORY_LEN: usize = 60;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Sample {
+    /// Tenths of a percent.
+    pub cpu_permille: u32,
+    pub heap_used: usize,
+    pub heap_total: usize,
+    pub rx_per_s: u64,
+    pub tx_per_s: u64,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ProcessRow {
+    pub pid: u16,
+    pub name: String,
+    pub ring: RingLevel,
+    pub active: bool,
+    pub threads: u8,
+    /// Tenths of a percent of the scheduler's dispatch capacity.
+    pub cpu_permille: u32,
+    pub mem_bytes: u64,
+    /// Bytes the process received and sent through the network API.
+    pub net_bytes: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SortKey {
+    Pid,
+    Name,
+    Cpu,
+    Memory,
+    Net,
+}
+
+impl SortKey {
+    pub const fn label(self) -> &'static str {
+        match self {
+            SortKey::Pid => "PID",
+            SortKey::Name => "Nombre",
+            SortKey::Cpu => "CPU",
+            SortKey::Memory => "Memoria",
+            SortKey::Net => "Red",
+        }
+    }
+}
+
+/// Sort `rows` by `key`; numbers sort largest first unless `ascending`.
+pub fn sort_rows(rows: &mut [ProcessRow], key: SortKey, ascending: bool) {
+    rows.sort_by(|a, b| {
+        let order = match key {
+            SortKey::Pid => a.pid.cmp(&b.pid),
+            SortKey::Name => a.name.to_ascii_lowercase().cmp(&b.name.to_ascii_lowercase()),
+            SortKey::Cpu => a.cpu_permille.cmp(&b.cpu_permille),
+            SortKey::Memory => a.mem_bytes.cmp(&b.mem_bytes),
+            SortKey::Net => a.net_bytes.cmp(&b.net_bytes),
+        };
+        let order = if ascending { order } else { order.reverse() };
+        order.then(a.pid.cmp(&b.pid))
+    });
+}
+
+/// Raw counters read at one instant; kept separate so the arithmetic can
+/// be tested without a scheduler.
+pub struct Counters {
+    pub now_us: u64,
+    pub ticks: u64,
+    pub cores: u32,
+    /// Busy microseconds per second of the desktop loop.
+    pub busy_us_per_s: u64,
+    pub heap_used: usize,
+    pub heap_total: usize,
+    pub net: (u64, u64),
+    /// (pid, name, ring, active, threads, runs)
+    pub processes: Vec<(u16, String, RingLevel, bool, u8, u64)>,
+}
+
+pub struct Monitor {
+    history: VecDeque<Sample>,
+    rows: Vec<ProcessRow>,
+    last_us: u64,
+    last_ticks: u64,
+    last_net: (u64, u64),
+    last_runs: Vec<(u16, u64)>,
+}
+
+impl Monitor {
+    pub const fn new() -> Self {
+        Self {
+            history: VecDeque::new(),
+            rows: Vec::new(),
+            last_us: 0,
+            last_ticks: 0,
+            last_net: (0, 0),
+            last_runs: Vec::new(),
+        }
+    }
+
+    pub fn due(&self, now_us: u64) -> bool {
+        self.last_us == 0 || now_us.saturating_sub(self.last_us) >= SAMPLE_INTERVAL_US
+    }
+
+    /// Fold in a new set of counters. The first call only sets the baseline
+    /// for rates; it still fills the process table.
+    pub fn update(&mut self, c: Counters) {
+        let first = self.last_us == 0;
+        let elapsed_us = c.now_us.saturating_sub(self.last_us).max(1);
+        let tick_capacity = c
+            .ticks
+            .saturating_sub(self.last_ticks)
+            .saturating_mul(c.cores.max(1) as u64);
+
+        let busy_permille = (c.busy_us_per_s / 1000).min(1000) as u32;
+        let mut rows = Vec::with_capacity(c.processes.len() + 1);
+        rows.push(ProcessRow {
+            pid: 0,
+            name: String::from("kernel"),
+            ring: RingLevel::Kernel,
+            active: true,
+            threads: c.cores.clamp(1, u8::MAX as u32) as u8,
+            cpu_permille: if first { 0 } else { busy_permille },
+            mem_bytes: c.heap_used as u64,
+            net_bytes: c.net.0.saturating_add(c.net.1),
+        });
+        let mut runs = Vec::with_capacity(c.processes.len());
+        for (pid, name, ring, active, threads, total_runs) in c.processes {
+            let before = self
+                .last_runs
+                .iter()
+                .find(|(p, _)| *p == pid)
+                .map(|(_, r)| *r)
+                .unwrap_or(total_runs);
+            let delta = total_runs.saturating_sub(before);
+            let cpu_permille = if first || tick_capacity == 0 || !active {
+                0
+            } else {
+                (delta.saturating_mul(1000) / tick_capacity).min(1000) as u32
+            };
+            runs.push((pid, total_runs));
+            rows.push(ProcessRow {
+                pid,
+                net_bytes: {
+                    let (rx, tx) = crate::net::stats::process_bytes(name.as_str());
+                    rx.saturating_add(tx)
+                },
+                name,
+                ring,
+                active,
+                threads,
+                cpu_permille,
+                mem_bytes: threads as u64 * crate::process::THREAD_STACK_BYTES as u64,
+            });
+        }
+        self.rows = rows;
+        self.last_runs = runs;
+
+        if !first {
+            let per_second = |delta: u64| delta.saturating_mul(1_000_000) / elapsed_us;
+            if self.history.len() == HISTORY_LEN {
+                self.history.pop_front();
+            }
+            self.history.push_back(Sample {
+                cpu_permille: busy_permille,
+                heap_used: c.heap_used,
+                heap_total: c.heap_total,
+                rx_per_s: per_second(c.net.0.saturating_sub(self.last_net.0)),
+                tx_per_s: per_second(c.net.1.saturating_sub(self.last_net.1)),
+            });
+        }
+        self.last_us = c.now_us.max(1);
+        self.last_ticks = c.ticks;
+        self.last_net = c.net;
+    }
+
+    pub fn history(&self) -> Vec<Sample> {
+        self.history.iter().copied().collect()
+    }
+
+    pub fn rows(&self) -> Vec<ProcessRow> {
+        self.rows.clone()
+    }
+}
+
+static MONITOR: SpinLock<Monitor> = SpinLock::new(Monitor::new());
+
+fn read_counters(now_us: u64) -> Counters {
+    let published = crate::perf::published();
+    let busy_per_frame: u64 = published.phase_us.iter().map(|&us| us as u64).sum();
+    let mut processes = Vec::new();
+    let mut index = 0usize;
+    while let Some(info) = crate::process::process_info(index) {
+        processes.push((
+            info.pid,
+            String::from(info.name_str()),
+            info.ring,
+            info.active,
+            info.threads,
+            info.runs,
+        ));
+        index += 1;
+    }
+    Counters {
+        now_us,
+        ticks: crate::timer::ticks(),
+        cores: crate::smp::cpu_count(),
+        busy_us_per_s: busy_per_frame.saturating_mul(published.fps as u64),
+        heap_used: crate::allocator::heap_used_bytes(),
+        heap_total: crate::allocator::heap_size_bytes(),
+        net: crate::net::traffic_bytes(),
+        processes,
+    }
+}
+
+/// Take a sample when the interval elapsed. Returns true when one was taken.
+pub fn sample_if_due() -> bool {
+    let now = crate::perf::now_us();
+    if !MONITOR.lock().due(now) {
+        return false;
+    }
+    // Read outside the monitor lock: process_info takes the scheduler lock.
+    let counters = read_counters(now);
+    MONITOR.lock().update(counters);
+    true
+}
+
+/// Sample now regardless of the interval, e.g. right after a process was
+/// killed so the table does not show it running until the next tick.
+pub fn refresh() {
+    let now = crate::perf::now_us();
+    let counters = read_counters(now);
+    MONITOR.lock().update(counters);
+}
+
+pub fn history() -> Vec<Sample> {
+    MONITOR.lock().history()
+}
+
+pub fn process_rows() -> Vec<ProcessRow> {
+    MONITOR.lock().rows()
+}
+
+/// Latest sample, if two have been taken yet.
+pub fn latest() -> Option<Sample> {
+    MONITOR.lock().history.back().copied()
+}
+
+crate::selftest::kernel_tests! {
+    "sysmon";
+
+    fn rates_and_cpu_share_between_samples() {
+        let procs = |runs_a: u64, runs_b: u64| {
+            alloc::vec![
+                (1u16, String::from("shell"), RingLevel::User, true, 1u8, runs_a),
+                (2u16, String::from("apps"), RingLevel::User, true, 2u8, runs_b),
+            ]
+        };
+        let counters = |now_us, ticks, net, processes| Counters {
+            now_us,
+            ticks,
+            cores: 2,
+            busy_us_per_s: 250_000,
+            heap_used: 10,
+            heap_total: 100,
+            net,
+            processes,
+        };
+        let mut m = Monitor::new();
+        m.update(counters(1_000_000, 100, (5_000, 1_000), procs(40, 10)));
+        crate::selftest::ensure(m.history().is_empty(), "la primera muestra es la base")?;
+        crate::selftest::ensure_eq(m.rows()[2].mem_bytes, 2 * crate::process::THREAD_STACK_BYTES as u64, "pila por hilo")?;
+        crate::selftest::ensure(!m.due(1_200_000) && m.due(1_500_000), "intervalo")?;
+        // 100 ticks on 2 cores = 200 dispatch slots.
+        m.update(counters(1_500_000, 200, (55_000, 6_000), procs(90, 30)));
+        let rows = m.rows();
+        crate::selftest::ensure_eq((rows[0].pid, rows[0].ring, rows[0].net_bytes), (0, RingLevel::Kernel, 61_000), "fila del kernel")?;
+        crate::selftest::ensure_eq((rows[1].cpu_permille, rows[2].cpu_permille), (250, 100), "cuota de CPU")?;
+        let s = m.history()[0];
+        crate::selftest::ensure_eq((s.cpu_permille, s.rx_per_s, s.tx_per_s), (250, 100_000, 10_000), "muestra")
+    }
+
+    fn history_is_bounded_and_sorting_is_stable() {
+        let mut m = Monitor::new();
+        for i in 0..HISTORY_LEN as u64 + 5 {
+            m.update(Counters {
+                now_us: (i + 1) * SAMPLE_INTERVAL_US,
+                ticks: i,
+                cores: 1,
+                busy_us_per_s: 0,
+                heap_used: i as usize,
+                heap_total: 100,
+                net: (0, 0),
+                processes: Vec::new(),
+            });
+        }
+        let history = m.history();
+        crate::selftest::ensure_eq(history.len(), HISTORY_LEN, "historial acotado")?;
+        crate::selftest::ensure_eq(history[HISTORY_LEN - 1].heap_used, HISTORY_LEN + 4, "la mas reciente al final")?;
+
+        let row = |pid, name: &str, cpu| ProcessRow {
+            pid,
+            name: String::from(name),
+            ring: RingLevel::User,
+            active: true,
+            threads: 1,
+            cpu_permille: cpu,
+            mem_bytes: 0,
+            net_bytes: 0,
+        };
+        let mut rows = alloc::vec![row(3, "b", 5), row(1, "C", 20), row(2, "a", 5)];
+        sort_rows(&mut rows, SortKey::Cpu, false);
+        crate::selftest::ensure_eq(rows.iter().map(|r| r.pid).collect::<Vec<_>>(), alloc::vec![1, 2, 3], "CPU desc")?;
+        sort_rows(&mut rows, SortKey::Name, true);
+        crate::selftest::ensure_eq(rows.iter().map(|r| r.pid).collect::<Vec<_>>(), alloc::vec![2, 3, 1], "nombre asc")
+    }
+}
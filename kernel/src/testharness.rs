@@ -0,0 +1,241 @@
+//! Headless test harness for QEMU-based integration tests.
+//!
+//! Enabled by booting with `testharness` in the image load options (e.g.
+//! `startup.nsh`: `\EFI\BOOT\BOOTX64.EFI testharness`) or by a `\REDUXTEST.INI`
+//! file on the boot volume. In that mode the kernel skips the installer and
+//! the GUI and serves a line protocol on COM2 (0x2F8), which OVMF leaves
+//! alone; COM1 keeps carrying the firmware console.
+//!
+//! Host -> kernel, one command per line:
+//!   `PING`                 -> `@@PONG`
+//!   `CMD <id> <command>`   -> `@@BEGIN <id>`, `@@OUT <id> <line>`..., `@@END <id> lines=<n>`
+//!   `LOG <n>`              -> last `n` kernel log records as `@@LOG <line>`
+//!   `SELFTEST [filter]`    -> `@@TEST` frames for the in-kernel registry, then `@@SUMMARY`
+//!   `EXIT <code>`          -> `@@BYE <code>` then isa-debug-exit
+//!
+//! Every frame is mirrored to the debugcon port (0xE9) so a run can be
+//! asserted on from the `-debugcon file:` log alone. `report_test` emits
+//! `@@TEST <name> PASS|FAIL <detail>` frames for in-kernel checks. Booting
+//! with `testharness selftest` runs `crate::selftest` right away and exits
+//! QEMU with the result, without needing a host-side client.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::hal::{inb, outb, outl};
+
+const DEBUGCON_PORT: u16 = 0xE9;
+const DEBUG_EXIT_PORT: u16 = 0xF4;
+const COM2_PORT: u16 = 0x2F8;
+const HARNESS_LINE_MAX: usize = 512;
+const HARNESS_CAPTURE_MAX: usize = 4096;
+const HARNESS_PROTOCOL: &str = "v1";
+
+/// Exit codes written to isa-debug-exit; QEMU exits with `(code << 1) | 1`.
+pub const EXIT_SUCCESS: u32 = 0;
+pub const EXIT_FAILURE: u32 = 1;
+
+struct HarnessState {
+    enabled: bool,
+    serial_ready: bool,
+    line: Vec<u8>,
+    passed: u32,
+    failed: u32,
+}
+
+static mut HARNESS: HarnessState = HarnessState {
+    enabled: false,
+    serial_ready: false,
+    line: Vec::new(),
+    passed: 0,
+    failed: 0,
+};
+
+pub fn is_enabled() -> bool {
+    unsafe { HARNESS.enabled }
+}
+
+/// QEMU's debugcon answers reads of port 0xE9 with 0xE9.
+pub fn debugcon_present() -> bool {
+    unsafe { inb(DEBUGCON_PORT) == 0xE9 }
+}
+
+fn debugcon_write(bytes: &[u8]) {
+    for b in bytes.iter() {
+        unsafe { outb(DEBUGCON_PORT, *b) };
+    }
+}
+
+fn serial_init() {
+    unsafe {
+        outb(COM2_PORT + 1, 0x00); // no interrupts
+        outb(COM2_PORT + 3, 0x80); // DLAB
+        outb(COM2_PORT, 0x01); // 115200 baud
+        outb(COM2_PORT + 1, 0x00);
+        outb(COM2_PORT + 3, 0x03); // 8N1
+        outb(COM2_PORT + 2, 0xC7); // FIFO on, cleared, 14-byte threshold
+        outb(COM2_PORT + 4, 0x03); // DTR + RTS
+        // A floating bus reads back 0xFF: no UART behind COM2.
+        HARNESS.serial_ready = inb(COM2_PORT + 5) != 0xFF;
+    }
+}
+
+fn serial_write(bytes: &[u8]) {
+    if !unsafe { HARNESS.serial_ready } {
+        return;
+    }
+    for b in bytes.iter() {
+        let mut spins = 0u32;
+        while unsafe { inb(COM2_PORT + 5) } & 0x20 == 0 && spins < 100_000 {
+            core::hint::spin_loop();
+            spins += 1;
+        }
+        unsafe { outb(COM2_PORT, *b) };
+    }
+}
+
+fn serial_read_byte() -> Option<u8> {
+    if !unsafe { HARNESS.serial_ready } {
+        return None;
+    }
+    if unsafe { inb(COM2_PORT + 5) } & 0x01 == 0 {
+        return None;
+    }
+    Some(unsafe { inb(COM2_PORT) })
+}
+
+/// Write one protocol frame to debugcon and the command channel.
+pub fn emit(frame: &str) {
+    let clean: String = frame.chars().map(|c| if c == '\n' || c == '\r' { ' ' } else { c }).collect();
+    debugcon_write(clean.as_bytes());
+    debugcon_write(b"\n");
+    serial_write(clean.as_bytes());
+    serial_write(b"\r\n");
+}
+
+/// Record the outcome of an in-kernel check.
+pub fn report_test(name: &str, passed: bool, detail: &str) {
+    unsafe {
+        if passed {
+            HARNESS.passed = HARNESS.passed.saturating_add(1);
+        } else {
+            HARNESS.failed = HARNESS.failed.saturating_add(1);
+        }
+    }
+    emit(alloc::format!("@@TEST {} {} {}", name, if passed { "PASS" } else { "FAIL" }, detail).as_str());
+}
+
+pub fn summary_line() -> String {
+    unsafe { alloc::format!("@@SUMMARY passed={} failed={}", HARNESS.passed, HARNESS.failed) }
+}
+
+/// Leave QEMU through isa-debug-exit. Halts if the device is not present.
+pub fn qemu_exit(code: u32) -> ! {
+    emit(alloc::format!("@@BYE {}", code).as_str());
+    unsafe { outl(DEBUG_EXIT_PORT, code) };
+    loop {
+        crate::hal::cli();
+        crate::hal::hlt();
+    }
+}
+
+/// True when the boot arguments or the boot volume ask for harness mode.
+pub fn requested(load_options: Option<&str>, marker_present: bool) -> bool {
+    marker_present
+        || load_options
+            .map(|opts| opts.split_whitespace().any(|w| w.eq_ignore_ascii_case("testharness")))
+            .unwrap_or(false)
+}
+
+fn run_command(id: &str, command: &str, fat: &mut crate::fat32::Fat32, current_cluster: &mut u32) {
+    emit(alloc::format!("@@BEGIN {}", id).as_str());
+    let start_seq = crate::klog::next_seq();
+    crate::handle_command(command, fat, current_cluster);
+    // Command output reaches the harness through the console log (`println`).
+    let records = crate::klog::records_since(start_seq, HARNESS_CAPTURE_MAX);
+    for record in records.iter() {
+        emit(alloc::format!("@@OUT {} {}", id, record.text).as_str());
+    }
+    emit(alloc::format!("@@END {} lines={}", id, records.len()).as_str());
+}
+
+fn dispatch(line: &str, fat: &mut crate::fat32::Fat32, current_cluster: &mut u32) {
+    let (verb, rest) = match line.split_once(' ') {
+        Some((verb, rest)) => (verb, rest.trim()),
+        None => (line, ""),
+    };
+    match verb {
+        "PING" => emit("@@PONG"),
+        "CMD" => {
+            let (id, command) = rest.split_once(' ').unwrap_or((rest, ""));
+            if id.is_empty() {
+                emit("@@ERR CMD requiere <id> <comando>");
+            } else {
+                run_command(id, command.trim(), fat, current_cluster);
+            }
+        }
+        "LOG" => {
+            let count = rest.parse::<usize>().unwrap_or(50);
+            for record in crate::klog::tail(count).iter() {
+                emit(alloc::format!("@@LOG {}", crate::klog::format_record(record)).as_str());
+            }
+            emit("@@LOGEND");
+        }
+        "SELFTEST" => {
+            crate::selftest::run(rest);
+            emit(summary_line().as_str());
+        }
+        "SUMMARY" => emit(summary_line().as_str()),
+        "EXIT" => {
+            emit(summary_line().as_str());
+            qemu_exit(rest.parse::<u32>().unwrap_or(EXIT_SUCCESS));
+        }
+        "" => {}
+        _ => emit(alloc::format!("@@ERR comando desconocido: {}", verb).as_str()),
+    }
+}
+
+/// Serve the command channel forever. Only returns control through `EXIT`.
+pub fn run(mut current_cluster: u32, autorun_selftest: bool) -> ! {
+    unsafe {
+        HARNESS.enabled = true;
+    }
+    serial_init();
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    crate::println(
+        alloc::format!(
+            "Test harness: activo (debugcon={}, COM2={})",
+            if debugcon_present() { "si" } else { "no" },
+            if unsafe { HARNESS.serial_ready } { "si" } else { "no" }
+        )
+        .as_str(),
+    );
+    emit(alloc::format!("@@READY {}", HARNESS_PROTOCOL).as_str());
+
+    if autorun_selftest {
+        crate::selftest::run("");
+        emit(summary_line().as_str());
+        let failed = unsafe { HARNESS.failed };
+        qemu_exit(if failed == 0 { EXIT_SUCCESS } else { EXIT_FAILURE });
+    }
+
+    loop {
+        let tick = crate::timer::on_tick();
+        crate::scheduler::on_tick(tick);
+        crate::net::poll();
+
+        while let Some(byte) = serial_read_byte() {
+            let state = unsafe { &mut HARNESS };
+            match byte {
+                b'\r' | b'\n' => {
+                    let line = String::from_utf8_lossy(&state.line).into_owned();
+                    state.line.clear();
+                    dispatch(line.trim(), fat, &mut current_cluster);
+                }
+                _ if state.line.len() < HARNESS_LINE_MAX => state.line.push(byte),
+                _ => {}
+            }
+        }
+        uefi::boot::stall(1_000);
+    }
+}
@@ -0,0 +1,598 @@
+//! Thunderbolt / USB4 PCIe tunnels: authorization before binding drivers.
+//!
+//! A dock's NIC or NVMe reaches the machine as ordinary PCIe functions behind
+//! a hotplug-capable root or downstream port of the Thunderbolt controller.
+//! Such a device can DMA into any memory, so `pci::scan` asks `admit` first
+//! and holds every function below an external port until the attachment is
+//! authorized; `authorize` then hands the functions to the normal driver
+//! binding path (`pci::bind_held`).
+//!
+//! `thunderbolt.security` mirrors the firmware security levels:
+//!
+//! - `none`: tunneled devices are bound right away;
+//! - `user` (default): the desktop asks every time a device shows up;
+//! - `secure`: asks once and remembers the device in `thunderbolt.allow.*`;
+//! - `dponly`: PCIe tunnels are never enabled (display and USB still work).
+//!
+//! The firmware (or its BIOS-assisted tunneling) still sets up the tunnel
+//! and the bus numbers; `poll` only watches the presence bit of the external
+//! slots and rescans the bus when it changes.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::config::ConfigValue;
+use crate::pci::{PciDevice, PciDeviceInfo, PciDriver, PciMatch};
+use crate::spinlock::SpinLock;
+
+pub const SECURITY_KEY: &str = "thunderbolt.security";
+const ALLOW_PREFIX: &str = "thunderbolt.allow.";
+const POLL_INTERVAL_MS: u64 = 2000;
+
+const PCI_CAP_EXPRESS: u8 = 0x10;
+const PCIE_TYPE_ROOT_PORT: u32 = 0x4;
+const PCIE_TYPE_DOWNSTREAM_PORT: u32 = 0x6;
+const PCIE_FLAGS_SLOT_IMPLEMENTED: u32 = 1 << 8;
+const SLOT_CAP_HOTPLUG_CAPABLE: u32 = 1 << 6;
+const SLOT_STATUS_PRESENCE: u32 = 1 << 6;
+
+/// Intel Thunderbolt host routers (NHI), Alpine Ridge through Barlow Ridge.
+const INTEL_NHI_IDS: &[u16] = &[
+    0x1575, 0x1577, 0x15bf, 0x15d2, 0x15d9, 0x15e8, 0x15eb, 0x1136, 0x1137, 0x8a0d, 0x8a17, 0x9a1b, 0x9a1d, 0x9a1f,
+    0x9a21, 0x463e, 0x466d, 0xa73e, 0xa76d, 0x7eb2, 0x7ec2, 0x7ec3, 0x5781, 0x5784,
+];
+
+const INTEL_NHI_MATCHES: [PciMatch; INTEL_NHI_IDS.len()] = {
+    let mut out = [PciMatch::device(0x8086, 0); INTEL_NHI_IDS.len()];
+    let mut i = 0;
+    while i < out.len() {
+        out[i] = PciMatch::device(0x8086, INTEL_NHI_IDS[i]);
+        i += 1;
+    }
+    out
+};
+
+/// Registered ahead of `xhci` so newer NHIs, which report the USB4 host
+/// interface class (0x0C03), are not mistaken for a USB controller.
+pub static PCI_DRIVER: PciDriver = PciDriver {
+    name: "thunderbolt",
+    ids: &INTEL_NHI_MATCHES,
+    probe: init,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Security {
+    None,
+    User,
+    Secure,
+    DpOnly,
+}
+
+impl Security {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "none" => Some(Security::None),
+            "user" => Some(Security::User),
+            "secure" => Some(Security::Secure),
+            "dponly" => Some(Security::DpOnly),
+            _ => None,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Security::None => "none",
+            Security::User => "user",
+            Security::Secure => "secure",
+            Security::DpOnly => "dponly",
+        }
+    }
+}
+
+pub fn security() -> Security {
+    Security::parse(crate::config::get_str(SECURITY_KEY, "user").as_str()).unwrap_or(Security::User)
+}
+
+/// What the policy does with a new attachment.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Bind,
+    Ask,
+    Refuse,
+}
+
+pub fn policy(level: Security, allowed: bool) -> Action {
+    match level {
+        Security::None => Action::Bind,
+        Security::User => Action::Ask,
+        Security::Secure if allowed => Action::Bind,
+        Security::Secure => Action::Ask,
+        Security::DpOnly => Action::Refuse,
+    }
+}
+
+/// A hotplug-capable port of the Thunderbolt controller and the bus range
+/// behind it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Port {
+    pub bus: u8,
+    pub slot: u8,
+    pub func: u8,
+    pub secondary: u8,
+    pub subordinate: u8,
+}
+
+impl Port {
+    pub fn covers(&self, bus: u8) -> bool {
+        self.secondary != 0 && bus >= self.secondary && bus <= self.subordinate
+    }
+
+    pub fn name(&self) -> String {
+        crate::device::pci_name(self.bus, self.slot, self.func)
+    }
+}
+
+/// The outermost external port above `bus`: a daisy-chained dock is one
+/// attachment with its upstream port.
+pub fn outermost(ports: &[Port], bus: u8) -> Option<Port> {
+    ports
+        .iter()
+        .filter(|p| p.covers(bus))
+        .max_by_key(|p| p.subordinate as u16 - p.secondary as u16)
+        .copied()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Decision {
+    Pending,
+    Authorized,
+    Denied,
+}
+
+#[derive(Clone)]
+pub struct Attachment {
+    pub port: Port,
+    pub functions: Vec<PciDeviceInfo>,
+    pub decision: Decision,
+    asked: bool,
+}
+
+impl Attachment {
+    /// Name of the first function, for prompts and listings.
+    pub fn description(&self) -> String {
+        let Some(first) = self.functions.first() else {
+            return String::from("?");
+        };
+        let dev = first.device;
+        let name = crate::pci_ids::device_name(dev.vendor_id, dev.device_id)
+            .unwrap_or(crate::pci_ids::class_name(first.class_code, first.sub_class));
+        alloc::format!("{} {}", crate::pci_ids::vendor_name(dev.vendor_id).unwrap_or("?"), name)
+    }
+
+    /// Key remembered by `secure` mode.
+    pub fn identity(&self) -> Option<String> {
+        self.functions.first().map(identity)
+    }
+}
+
+pub fn identity(info: &PciDeviceInfo) -> String {
+    alloc::format!(
+        "{:04x}-{:04x}-{:04x}-{:04x}",
+        info.device.vendor_id,
+        info.device.device_id,
+        info.subsys_vendor_id,
+        info.subsys_id
+    )
+}
+
+struct State {
+    host_router: bool,
+    ports: Vec<Port>,
+    /// Slot presence of every port at the last poll.
+    presence: Vec<bool>,
+    attachments: Vec<Attachment>,
+    next_poll_ms: u64,
+}
+
+static STATE: SpinLock<State> = SpinLock::new(State {
+    host_router: false,
+    ports: Vec::new(),
+    presence: Vec::new(),
+    attachments: Vec::new(),
+    next_poll_ms: 0,
+});
+
+fn init(device: PciDevice) {
+    let node = crate::device::add_class_device(
+        Some(crate::device::pci_function(&device)),
+        "domain",
+        "thunderbolt",
+        "thunderbolt",
+    );
+    crate::device::set_live(node, "security", || String::from(security().name()));
+}
+
+fn is_host_router(info: &PciDeviceInfo) -> bool {
+    (info.class_code == 0x0C && info.sub_class == 0x03 && info.prog_if == 0x40)
+        || (info.device.vendor_id == 0x8086 && INTEL_NHI_IDS.contains(&info.device.device_id))
+}
+
+fn express_cap(bus: u8, slot: u8, func: u8) -> Option<u8> {
+    crate::pci::capabilities(bus, slot, func)
+        .into_iter()
+        .find(|(id, _)| *id == PCI_CAP_EXPRESS)
+        .map(|(_, offset)| offset)
+}
+
+/// External port described by a bridge, if it is one.
+fn external_port(info: &PciDeviceInfo) -> Option<Port> {
+    if info.class_code != 0x06 || info.sub_class != 0x04 {
+        return None;
+    }
+    let dev = info.device;
+    let cap = express_cap(dev.bus, dev.slot, dev.func)?;
+    let flags = unsafe { crate::pci::read_config(dev.bus, dev.slot, dev.func, cap) } >> 16;
+    let kind = (flags >> 4) & 0xF;
+    if (kind != PCIE_TYPE_ROOT_PORT && kind != PCIE_TYPE_DOWNSTREAM_PORT) || flags & PCIE_FLAGS_SLOT_IMPLEMENTED == 0 {
+        return None;
+    }
+    let slot_cap = unsafe { crate::pci::read_config(dev.bus, dev.slot, dev.func, cap + 0x14) };
+    if slot_cap & SLOT_CAP_HOTPLUG_CAPABLE == 0 {
+        return None;
+    }
+    let buses = unsafe { crate::pci::read_config(dev.bus, dev.slot, dev.func, 0x18) };
+    Some(Port {
+        bus: dev.bus,
+        slot: dev.slot,
+        func: dev.func,
+        secondary: ((buses >> 8) & 0xFF) as u8,
+        subordinate: ((buses >> 16) & 0xFF) as u8,
+    })
+}
+
+fn slot_present(port: &Port) -> bool {
+    let Some(cap) = express_cap(port.bus, port.slot, port.func) else {
+        return false;
+    };
+    let status = unsafe { crate::pci::read_config(port.bus, port.slot, port.func, cap + 0x18) } >> 16;
+    status & SLOT_STATUS_PRESENCE != 0
+}
+
+/// Called by `pci::scan` before binding: find the external ports and forget
+/// functions that are gone.
+pub fn update_topology(devices: &[PciDeviceInfo]) {
+    let host_router = devices.iter().any(is_host_router);
+    let ports: Vec<Port> = if host_router {
+        devices.iter().filter_map(external_port).collect()
+    } else {
+        Vec::new()
+    };
+    let presence = ports.iter().map(slot_present).collect();
+    let mut state = STATE.lock();
+    state.host_router = host_router;
+    state.attachments.retain(|a| ports.contains(&a.port));
+    for attachment in state.attachments.iter_mut() {
+        attachment.functions.retain(|f| {
+            devices.iter().any(|d| {
+                d.device.bus == f.device.bus
+                    && d.device.slot == f.device.slot
+                    && d.device.func == f.device.func
+                    && d.device.vendor_id == f.device.vendor_id
+            })
+        });
+    }
+    state.attachments.retain(|a| !a.functions.is_empty());
+    state.ports = ports;
+    state.presence = presence;
+}
+
+/// Whether `pci::scan` may bind `info` now. Tunneled functions of an
+/// attachment that isn't authorized are recorded and held.
+pub fn admit(info: &PciDeviceInfo) -> bool {
+    if info.class_code == 0x06 && info.sub_class == 0x04 {
+        return true;
+    }
+    let mut state = STATE.lock();
+    let Some(port) = outermost(&state.ports, info.device.bus) else {
+        return true;
+    };
+    let index = match state.attachments.iter().position(|a| a.port == port) {
+        Some(index) => index,
+        None => {
+            state.attachments.push(Attachment {
+                port,
+                functions: Vec::new(),
+                decision: Decision::Pending,
+                asked: false,
+            });
+            state.attachments.len() - 1
+        }
+    };
+    let attachment = &mut state.attachments[index];
+    if !attachment.functions.iter().any(|f| {
+        f.device.bus == info.device.bus && f.device.slot == info.device.slot && f.device.func == info.device.func
+    }) {
+        attachment.functions.push(*info);
+    }
+    let authorized = attachment.decision == Decision::Authorized;
+    crate::device::set_attr(
+        crate::device::pci_function(&info.device),
+        "authorized",
+        if authorized { "1" } else { "0" },
+    );
+    authorized
+}
+
+fn allowed(identity: &str) -> bool {
+    crate::config::get_bool(alloc::format!("{}{}", ALLOW_PREFIX, identity).as_str(), false)
+}
+
+/// Bind or refuse the attachments the policy decides on its own. Run after
+/// every scan and from `poll`, once the configuration can be read.
+pub fn apply_policy() {
+    let level = security();
+    let pending: Vec<(Port, Option<String>)> = STATE
+        .lock()
+        .attachments
+        .iter()
+        .filter(|a| a.decision == Decision::Pending && !a.functions.is_empty())
+        .map(|a| (a.port, a.identity()))
+        .collect();
+    for (port, identity) in pending {
+        let allowed = identity.as_deref().is_some_and(allowed);
+        match policy(level, allowed) {
+            Action::Bind => {
+                let _ = authorize(port);
+            }
+            Action::Refuse => {
+                let _ = deny(port);
+                crate::gui::notifications::post(
+                    "thunderbolt",
+                    "Thunderbolt",
+                    "Dispositivo PCIe bloqueado (seguridad dponly).",
+                    crate::gui::notifications::Urgency::Warning,
+                );
+            }
+            Action::Ask => {}
+        }
+    }
+}
+
+/// Next attachment waiting for the user; it is not offered again.
+pub fn next_prompt() -> Option<Attachment> {
+    let mut state = STATE.lock();
+    let attachment = state
+        .attachments
+        .iter_mut()
+        .find(|a| a.decision == Decision::Pending && !a.asked && !a.functions.is_empty())?;
+    attachment.asked = true;
+    Some(attachment.clone())
+}
+
+/// Enable the attachment behind `port` and bind its functions. Returns the
+/// drivers that took them.
+pub fn authorize(port: Port) -> Result<Vec<&'static str>, &'static str> {
+    let functions = {
+        let mut state = STATE.lock();
+        let attachment = state
+            .attachments
+            .iter_mut()
+            .find(|a| a.port == port)
+            .ok_or("no hay ningun dispositivo en ese puerto")?;
+        attachment.decision = Decision::Authorized;
+        attachment.functions.clone()
+    };
+    if security() == Security::Secure {
+        if let Some(first) = functions.first() {
+            let key = alloc::format!("{}{}", ALLOW_PREFIX, identity(first));
+            let _ = crate::config::set(key.as_str(), ConfigValue::Bool(true));
+        }
+    }
+    let mut bound = Vec::new();
+    for info in functions.iter() {
+        crate::device::set_attr(crate::device::pci_function(&info.device), "authorized", "1");
+        if let Some(driver) = crate::pci::bind_held(&info.device) {
+            bound.push(driver);
+        }
+    }
+    crate::klog::log(
+        crate::klog::Level::Info,
+        alloc::format!(
+            "thunderbolt: {} autorizado, {} funciones con driver",
+            port.name(),
+            bound.len()
+        )
+        .as_str(),
+    );
+    Ok(bound)
+}
+
+/// Leave the attachment behind `port` unbound until it is plugged in again.
+pub fn deny(port: Port) -> Result<(), &'static str> {
+    let mut state = STATE.lock();
+    let attachment = state
+        .attachments
+        .iter_mut()
+        .find(|a| a.port == port)
+        .ok_or("no hay ningun dispositivo en ese puerto")?;
+    attachment.decision = Decision::Denied;
+    Ok(())
+}
+
+/// Rescan the bus when an external slot gains or loses a device.
+pub fn poll() {
+    let now = crate::timer::now_ms();
+    let changed = {
+        let mut state = STATE.lock();
+        if state.ports.is_empty() || now < state.next_poll_ms {
+            return;
+        }
+        state.next_poll_ms = now + POLL_INTERVAL_MS;
+        let presence: Vec<bool> = state.ports.iter().map(slot_present).collect();
+        let changed = presence != state.presence;
+        state.presence = presence;
+        changed
+    };
+    if changed {
+        let report = crate::pci::scan();
+        crate::klog::log(
+            crate::klog::Level::Info,
+            alloc::format!("thunderbolt: puerto cambiado, {} funciones nuevas", report.added).as_str(),
+        );
+    }
+    apply_policy();
+}
+
+fn find_port(text: &str) -> Option<Port> {
+    let state = STATE.lock();
+    let text = text.trim();
+    if let Ok(index) = text.parse::<usize>() {
+        return state.attachments.get(index).map(|a| a.port);
+    }
+    state
+        .attachments
+        .iter()
+        .find(|a| a.port.name() == text || a.port.name().ends_with(text))
+        .map(|a| a.port)
+}
+
+/// Shared implementation of
+/// `thunderbolt [status|authorize <n>|deny <n>|security <nivel>|forget]`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim();
+    let (sub, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    match sub {
+        "" | "status" => {
+            let state = STATE.lock();
+            let mut out = alloc::vec![
+                alloc::format!(
+                    "Thunderbolt/USB4: {}",
+                    if state.host_router {
+                        "controlador presente"
+                    } else {
+                        "sin controlador"
+                    }
+                ),
+                alloc::format!("Seguridad: {}", security().name()),
+                alloc::format!("Puertos externos: {}", state.ports.len()),
+            ];
+            for (index, attachment) in state.attachments.iter().enumerate() {
+                out.push(alloc::format!(
+                    "  [{}] puerto {}  {}  {} funciones  {}",
+                    index,
+                    attachment.port.name(),
+                    attachment.description(),
+                    attachment.functions.len(),
+                    match attachment.decision {
+                        Decision::Pending => "pendiente",
+                        Decision::Authorized => "autorizado",
+                        Decision::Denied => "denegado",
+                    }
+                ));
+            }
+            out
+        }
+        "authorize" | "deny" => {
+            let Some(port) = find_port(rest) else {
+                return alloc::vec![alloc::format!("Uso: thunderbolt {} <n|bus:slot.func>", sub)];
+            };
+            if sub == "deny" {
+                return match deny(port) {
+                    Ok(()) => alloc::vec![alloc::format!("Thunderbolt: {} denegado.", port.name())],
+                    Err(e) => alloc::vec![alloc::format!("thunderbolt: {}", e)],
+                };
+            }
+            match authorize(port) {
+                Ok(drivers) if drivers.is_empty() => {
+                    alloc::vec![alloc::format!(
+                        "Thunderbolt: {} autorizado (ningun driver lo reclama).",
+                        port.name()
+                    )]
+                }
+                Ok(drivers) => alloc::vec![alloc::format!(
+                    "Thunderbolt: {} autorizado -> {}",
+                    port.name(),
+                    drivers.join(", ")
+                )],
+                Err(e) => alloc::vec![alloc::format!("thunderbolt: {}", e)],
+            }
+        }
+        "security" => {
+            let Some(level) = Security::parse(rest) else {
+                return alloc::vec![String::from("Uso: thunderbolt security none|user|secure|dponly")];
+            };
+            match crate::config::set(SECURITY_KEY, ConfigValue::Str(String::from(level.name()))) {
+                Ok(()) => {
+                    apply_policy();
+                    alloc::vec![alloc::format!("Seguridad Thunderbolt: {}", level.name())]
+                }
+                Err(e) => alloc::vec![alloc::format!("config: {}", e)],
+            }
+        }
+        "forget" => {
+            let mut removed = 0;
+            for (key, _) in crate::config::list(ALLOW_PREFIX) {
+                if crate::config::unset(key.as_str()).unwrap_or(false) {
+                    removed += 1;
+                }
+            }
+            alloc::vec![alloc::format!("Thunderbolt: {} dispositivos olvidados.", removed)]
+        }
+        _ => alloc::vec![String::from(
+            "Uso: thunderbolt [status|authorize <n>|deny <n>|security none|user|secure|dponly|forget]"
+        )],
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "thunderbolt";
+
+    fn security_levels_pick_the_action() {
+        crate::selftest::ensure_eq(Security::parse(" DPonly"), Some(Security::DpOnly), "nivel")?;
+        crate::selftest::ensure_eq(Security::parse("sl1"), None, "desconocido")?;
+        crate::selftest::ensure_eq(policy(Security::None, false), Action::Bind, "none")?;
+        crate::selftest::ensure_eq(policy(Security::User, true), Action::Ask, "user pregunta siempre")?;
+        crate::selftest::ensure_eq(policy(Security::Secure, false), Action::Ask, "secure nuevo")?;
+        crate::selftest::ensure_eq(policy(Security::Secure, true), Action::Bind, "secure recordado")?;
+        crate::selftest::ensure_eq(policy(Security::DpOnly, true), Action::Refuse, "dponly")
+    }
+
+    fn functions_group_under_the_outermost_port() {
+        let port = |slot, secondary, subordinate| Port {
+            bus: 0,
+            slot,
+            func: 0,
+            secondary,
+            subordinate,
+        };
+        // Root port 0:07.0 owns buses 5..=40; a dock behind it has its own
+        // hotplug port for buses 8..=20.
+        let ports = [port(7, 5, 40), port(9, 8, 20), port(13, 41, 60)];
+        crate::selftest::ensure_eq(outermost(&ports, 12), Some(ports[0]), "dock encadenado")?;
+        crate::selftest::ensure_eq(outermost(&ports, 41), Some(ports[2]), "segundo puerto")?;
+        crate::selftest::ensure_eq(outermost(&ports, 2), None, "bus interno")?;
+        crate::selftest::ensure(!port(3, 0, 0).covers(0), "puente sin buses asignados")
+    }
+
+    fn identity_is_a_valid_config_key() {
+        let info = PciDeviceInfo {
+            device: PciDevice {
+                bus: 6,
+                slot: 0,
+                func: 0,
+                vendor_id: 0x8086,
+                device_id: 0x15F2,
+            },
+            class_code: 0x02,
+            sub_class: 0x00,
+            prog_if: 0,
+            revision: 0,
+            subsys_vendor_id: 0x1028,
+            subsys_id: 0x0A1B,
+            driver: None,
+        };
+        let key = alloc::format!("{}{}", ALLOW_PREFIX, identity(&info));
+        crate::selftest::ensure_eq(key.as_str(), "thunderbolt.allow.8086-15f2-1028-0a1b", "clave")?;
+        crate::selftest::ensure(crate::config::valid_key(key.as_str()), "clave valida")
+    }
+}
@@ -1,6 +1,17 @@
-use core::sync::atomic::{AtomicI32, AtomicI64, AtomicU64, Ordering};
+//! Tick counter, wall clock and the kernel timer wheel.
+//!
+//! Timers are armed in milliseconds on the TSC-backed clock of
+//! [`now_ms`] and fire from [`run_due`], which the shell, desktop and runtime
+//! loops call once per iteration; callbacks therefore run on the kernel
+//! thread and may arm or cancel timers themselves. In runtime IRQ mode the
+//! earliest deadline is also handed to the local APIC so a Linux slice is
+//! cut short when a timer comes due.
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicI32, AtomicI64, AtomicU64, Ordering};
 
 use crate::hal::outb;
+use crate::spinlock::SpinLock;
 
 #[derive(Clone, Copy)]
 pub struct TickSnapshot {
@@ -103,3 +114,236 @@ pub fn set_wall_clock_from_local_unix_seconds(local_seconds: i64, timezone_offse
         timezone_offset_minutes,
     );
 }
+
+/// Called with the argument the timer was armed with.
+pub type TimerFn = fn(usize);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimerId(u64);
+
+impl TimerId {
+    pub const fn raw(self) -> u64 {
+        self.0
+    }
+
+    /// Rebuild an id kept in an atomic; 0 means no timer.
+    pub const fn from_raw(raw: u64) -> Option<Self> {
+        if raw == 0 {
+            None
+        } else {
+            Some(Self(raw))
+        }
+    }
+}
+
+struct TimerEntry {
+    id: u64,
+    deadline_ms: u64,
+    /// 0 for one-shot timers.
+    period_ms: u64,
+    callback: TimerFn,
+    arg: usize,
+}
+
+/// One slot per millisecond; a timer further out than a turn waits in its
+/// slot until the cursor has gone round enough times.
+const WHEEL_SLOTS: usize = 256;
+
+struct Wheel {
+    slots: [Vec<TimerEntry>; WHEEL_SLOTS],
+    /// First millisecond not yet processed.
+    cursor_ms: u64,
+    next_id: u64,
+    armed: usize,
+}
+
+impl Wheel {
+    fn insert(&mut self, entry: TimerEntry) {
+        let slot = (entry.deadline_ms % WHEEL_SLOTS as u64) as usize;
+        self.slots[slot].push(entry);
+        self.armed += 1;
+    }
+
+    fn earliest_deadline_ms(&self) -> Option<u64> {
+        self.slots.iter().flatten().map(|entry| entry.deadline_ms).min()
+    }
+}
+
+static WHEEL: SpinLock<Wheel> = SpinLock::new(Wheel {
+    slots: [const { Vec::new() }; WHEEL_SLOTS],
+    cursor_ms: 0,
+    next_id: 1,
+    armed: 0,
+});
+static RUNNING_DUE: AtomicBool = AtomicBool::new(false);
+
+/// Milliseconds since boot on the monotonic clock (`clocksource`). Unlike
+/// `ticks()` it does not depend on the tick rate of the current runtime mode.
+pub fn now_ms() -> u64 {
+    crate::clocksource::monotonic_ns() / 1_000_000
+}
+
+fn arm(delay_ms: u64, period_ms: u64, callback: TimerFn, arg: usize) -> TimerId {
+    let now = now_ms();
+    let (id, earliest) = {
+        let mut wheel = WHEEL.lock();
+        let id = wheel.next_id;
+        wheel.next_id += 1;
+        // Never behind the cursor, or the slot would only be visited a turn later.
+        let deadline_ms = now.saturating_add(delay_ms).max(wheel.cursor_ms);
+        wheel.insert(TimerEntry {
+            id,
+            deadline_ms,
+            period_ms,
+            callback,
+            arg,
+        });
+        (id, wheel.earliest_deadline_ms())
+    };
+    crate::interrupts::set_timer_wakeup_ms(earliest);
+    TimerId(id)
+}
+
+/// Run `callback(arg)` once, `delay_ms` from now.
+pub fn after_ms(delay_ms: u64, callback: TimerFn, arg: usize) -> TimerId {
+    arm(delay_ms, 0, callback, arg)
+}
+
+/// Run `callback(arg)` every `period_ms` until cancelled. Periods missed
+/// while the kernel was busy are skipped, not replayed.
+pub fn every_ms(period_ms: u64, callback: TimerFn, arg: usize) -> TimerId {
+    let period_ms = period_ms.max(1);
+    arm(period_ms, period_ms, callback, arg)
+}
+
+/// Disarm a timer. Returns false if it already fired (one-shot) or was
+/// cancelled before.
+pub fn cancel(id: TimerId) -> bool {
+    let mut wheel = WHEEL.lock();
+    for slot in wheel.slots.iter_mut() {
+        if let Some(index) = slot.iter().position(|entry| entry.id == id.0) {
+            slot.swap_remove(index);
+            wheel.armed -= 1;
+            return true;
+        }
+    }
+    false
+}
+
+pub fn armed_count() -> usize {
+    WHEEL.lock().armed
+}
+
+/// Fire every timer whose deadline has passed; returns how many ran.
+pub fn run_due() -> usize {
+    if RUNNING_DUE.swap(true, Ordering::Acquire) {
+        return 0;
+    }
+    let now = now_ms();
+    let mut due: Vec<(TimerFn, usize)> = Vec::new();
+    let earliest = {
+        let mut wheel = WHEEL.lock();
+        if wheel.armed > 0 && now >= wheel.cursor_ms {
+            let span = (now - wheel.cursor_ms + 1).min(WHEEL_SLOTS as u64);
+            let mut fired: Vec<TimerEntry> = Vec::new();
+            for ms in wheel.cursor_ms..wheel.cursor_ms + span {
+                let slot = &mut wheel.slots[(ms % WHEEL_SLOTS as u64) as usize];
+                let mut index = 0;
+                while index < slot.len() {
+                    if slot[index].deadline_ms <= now {
+                        fired.push(slot.swap_remove(index));
+                    } else {
+                        index += 1;
+                    }
+                }
+            }
+            wheel.armed -= fired.len();
+            // Re-arm periodic timers before running anything so a callback
+            // can cancel its own timer.
+            for mut entry in fired {
+                due.push((entry.callback, entry.arg));
+                if entry.period_ms > 0 {
+                    entry.deadline_ms = entry.deadline_ms.saturating_add(entry.period_ms);
+                    if entry.deadline_ms <= now {
+                        entry.deadline_ms = now + entry.period_ms;
+                    }
+                    wheel.insert(entry);
+                }
+            }
+        }
+        wheel.cursor_ms = wheel.cursor_ms.max(now + 1);
+        wheel.earliest_deadline_ms()
+    };
+    crate::interrupts::set_timer_wakeup_ms(earliest);
+    for (callback, arg) in due.iter() {
+        callback(*arg);
+    }
+    RUNNING_DUE.store(false, Ordering::Release);
+    due.len()
+}
+
+/// A point on the [`now_ms`] clock, for blocking loops that wait on a device
+/// or a peer and never return to [`run_due`] while they spin.
+#[derive(Clone, Copy, Debug)]
+pub struct Deadline {
+    at_ms: u64,
+}
+
+impl Deadline {
+    pub fn after_ms(ms: u64) -> Self {
+        Self {
+            at_ms: now_ms().saturating_add(ms),
+        }
+    }
+
+    pub fn expired(&self) -> bool {
+        now_ms() >= self.at_ms
+    }
+
+    pub fn remaining_ms(&self) -> u64 {
+        self.at_ms.saturating_sub(now_ms())
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "timer";
+
+    fn one_shot_fires_once_and_cancel_disarms() {
+        static FIRED: AtomicU64 = AtomicU64::new(0);
+        fn bump(arg: usize) {
+            FIRED.fetch_add(arg as u64, Ordering::SeqCst);
+        }
+
+        FIRED.store(0, Ordering::SeqCst);
+        let before = armed_count();
+        let _fired = after_ms(0, bump, 1);
+        let cancelled = after_ms(60_000, bump, 100);
+        crate::selftest::ensure_eq(armed_count(), before + 2, "armados")?;
+        let deadline = Deadline::after_ms(50);
+        while FIRED.load(Ordering::SeqCst) == 0 && !deadline.expired() {
+            run_due();
+        }
+        run_due();
+        crate::selftest::ensure_eq(FIRED.load(Ordering::SeqCst), 1, "disparos")?;
+        crate::selftest::ensure(cancel(cancelled), "cancelar")?;
+        crate::selftest::ensure(!cancel(cancelled), "cancelar dos veces")?;
+        crate::selftest::ensure_eq(armed_count(), before, "sin pendientes")
+    }
+
+    fn periodic_rearms_until_cancelled() {
+        static FIRED: AtomicU64 = AtomicU64::new(0);
+        fn bump(_: usize) {
+            FIRED.fetch_add(1, Ordering::SeqCst);
+        }
+
+        FIRED.store(0, Ordering::SeqCst);
+        let id = every_ms(1, bump, 0);
+        let deadline = Deadline::after_ms(50);
+        while FIRED.load(Ordering::SeqCst) < 2 && !deadline.expired() {
+            run_due();
+        }
+        let still_armed = cancel(id);
+        crate::selftest::ensure(FIRED.load(Ordering::SeqCst) >= 2, "repeticiones")?;
+        crate::selftest::ensure(still_armed, "sigue armado")
+    }
+}
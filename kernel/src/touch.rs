@@ -0,0 +1,849 @@
+//! USB HID touch screens (digitizer page, Touch Screen application), read
+//! through the firmware's USB bus driver like the gamepads.
+//!
+//! Contacts come from the report descriptor: every Finger collection is one
+//! slot with tip switch, contact id and X/Y, plus an optional Contact Count
+//! for devices that spread the fingers over several reports ("hybrid" mode).
+//! Positions are scaled from the descriptor's logical range to the current
+//! display resolution, turned by `input.touch.orientation` for panels that
+//! are mounted rotated, and translated into pointer reports:
+//!
+//! - tap: left click where the finger was put down;
+//! - touch and move, or hold for `PRESS_DELAY_MS`: left drag;
+//! - two fingers moving up/down: wheel steps (content follows the fingers);
+//! - two-finger tap: right click.
+//!
+//! `event_queue::pump` queues the reports as ordinary mouse events with an
+//! absolute position.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::config::ConfigValue;
+use crate::spinlock::SpinLock;
+use crate::usbhid::{
+    extended_usage, hid_set_idle, interrupt_in_endpoint, read_bits, read_hid_report_descriptor, sign_extend,
+    HidGlobals, RawUsbIoProtocol, UsbDeviceDescriptor, UsbInterfaceDescriptor,
+};
+
+pub const ORIENTATION_KEY: &str = "input.touch.orientation";
+const MAX_DEVICES: usize = 2;
+const MAX_CONTACTS: usize = 10;
+const REPORT_MAX_BYTES: usize = 256;
+const EVENT_QUEUE_LIMIT: usize = 256;
+/// Contacts are normalised to 0..=NORM_MAX on both axes.
+const NORM_MAX: i32 = 65535;
+
+/// Movement that still counts as a tap, in screen pixels.
+pub const TAP_SLOP_PX: i32 = 10;
+/// A finger held this long without moving presses the left button.
+pub const PRESS_DELAY_MS: u64 = 150;
+pub const TWO_FINGER_TAP_MS: u64 = 300;
+/// Two-finger travel per wheel step.
+pub const SCROLL_STEP_PX: i32 = 24;
+
+const PAGE_GENERIC_DESKTOP: u16 = 0x01;
+const PAGE_DIGITIZER: u16 = 0x0D;
+const USAGE_TOUCH_SCREEN: u16 = 0x04;
+const USAGE_FINGER: u16 = 0x22;
+const USAGE_TIP_SWITCH: u16 = 0x42;
+const USAGE_CONTACT_ID: u16 = 0x51;
+const USAGE_CONTACT_COUNT: u16 = 0x54;
+
+// ---------------------------------------------------------------------------
+// HID report descriptors
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Field {
+    report_id: u8,
+    bit_offset: u32,
+    bit_size: u8,
+    logical_min: i32,
+    logical_max: i32,
+}
+
+impl Field {
+    fn read(&self, payload: &[u8]) -> Option<i32> {
+        let raw = read_bits(payload, self.bit_offset, self.bit_size)?;
+        Some(if self.logical_min < 0 {
+            sign_extend(raw, self.bit_size)
+        } else {
+            raw as i32
+        })
+    }
+
+    /// Value scaled to 0..=NORM_MAX over the logical range.
+    fn read_normalised(&self, payload: &[u8]) -> Option<i32> {
+        let value = self.read(payload)?;
+        if self.logical_max <= self.logical_min {
+            return Some(0);
+        }
+        let scaled = (value as i64 - self.logical_min as i64) * NORM_MAX as i64
+            / (self.logical_max as i64 - self.logical_min as i64);
+        Some(scaled.clamp(0, NORM_MAX as i64) as i32)
+    }
+}
+
+/// One finger's fields.
+#[derive(Clone, Copy, Default, Debug)]
+struct Slot {
+    tip: Option<Field>,
+    id: Option<Field>,
+    x: Option<Field>,
+    y: Option<Field>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TouchLayout {
+    slots: Vec<Slot>,
+    contact_count: Option<Field>,
+    uses_report_ids: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Contact {
+    pub id: u32,
+    pub tip: bool,
+    /// 0..=65535 across the panel.
+    pub x: i32,
+    pub y: i32,
+}
+
+/// `None` unless the descriptor has a digitizer Touch Screen collection with
+/// at least one X/Y pair.
+pub fn parse_report_descriptor(desc: &[u8]) -> Option<TouchLayout> {
+    let mut layout = TouchLayout::default();
+    let mut globals = HidGlobals::default();
+    let mut stack: Vec<HidGlobals> = Vec::new();
+    let mut usages: Vec<(u16, u16)> = Vec::new();
+    let mut offsets: Vec<(u8, u32)> = Vec::new();
+    let mut depth = 0u32;
+    let mut in_touch = false;
+    // (collection depth, slot) of the Finger collection being read.
+    let mut finger: Option<(u32, usize)> = None;
+    // Slot for X/Y/tip found outside any Finger collection (single touch).
+    let mut loose: Option<usize> = None;
+
+    let mut i = 0usize;
+    while i < desc.len() {
+        let prefix = desc[i];
+        if prefix == 0xFE {
+            let size = *desc.get(i + 1)? as usize;
+            i += 3 + size;
+            continue;
+        }
+        let size = [0usize, 1, 2, 4][(prefix & 0x03) as usize];
+        if i + 1 + size > desc.len() {
+            break;
+        }
+        let raw = &desc[i + 1..i + 1 + size];
+        let data = raw.iter().rev().fold(0u32, |acc, b| (acc << 8) | *b as u32);
+        let sdata = match size {
+            1 => data as u8 as i8 as i32,
+            2 => data as u16 as i16 as i32,
+            _ => data as i32,
+        };
+        i += 1 + size;
+
+        match prefix & 0xFC {
+            0x04 => globals.page = data as u16,
+            0x14 => globals.logical_min = sdata,
+            0x24 => {
+                globals.logical_max_signed = sdata;
+                globals.logical_max_unsigned = data as i32;
+            }
+            0x74 => globals.report_size = data,
+            0x84 => {
+                globals.report_id = data as u8;
+                layout.uses_report_ids = true;
+            }
+            0x94 => globals.report_count = data,
+            0xA4 => stack.push(globals),
+            0xB4 => globals = stack.pop().unwrap_or(globals),
+            0x08 => usages.push(extended_usage(globals.page, data, size)),
+            0xA0 => {
+                depth += 1;
+                let usage = usages.first().copied();
+                let finger_starts = in_touch && usage == Some((PAGE_DIGITIZER, USAGE_FINGER));
+                if depth == 1 && data == 0x01 {
+                    in_touch = usage == Some((PAGE_DIGITIZER, USAGE_TOUCH_SCREEN));
+                } else if finger_starts && layout.slots.len() < MAX_CONTACTS {
+                    layout.slots.push(Slot::default());
+                    finger = Some((depth, layout.slots.len() - 1));
+                }
+                usages.clear();
+            }
+            0xC0 => {
+                if finger.is_some_and(|(at, _)| at == depth) {
+                    finger = None;
+                }
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    in_touch = false;
+                }
+                usages.clear();
+            }
+            0x80 => {
+                let constant = data & 0x01 != 0;
+                let variable = data & 0x02 != 0;
+                let bits = globals.report_size;
+                let cursor = match offsets.iter_mut().find(|(id, _)| *id == globals.report_id) {
+                    Some((_, cursor)) => cursor,
+                    None => {
+                        offsets.push((globals.report_id, 0));
+                        &mut offsets.last_mut().unwrap().1
+                    }
+                };
+                for n in 0..globals.report_count {
+                    let Some(&usage) = usages.get((n as usize).min(usages.len().saturating_sub(1))) else {
+                        break;
+                    };
+                    if !in_touch || constant || !variable || !(1..=32).contains(&bits) {
+                        continue;
+                    }
+                    let field = Field {
+                        report_id: globals.report_id,
+                        bit_offset: *cursor + n * bits,
+                        bit_size: bits as u8,
+                        logical_min: globals.logical_min,
+                        logical_max: globals.logical_max(),
+                    };
+                    if usage == (PAGE_DIGITIZER, USAGE_CONTACT_COUNT) {
+                        layout.contact_count = Some(field);
+                        continue;
+                    }
+                    if !matches!(
+                        usage,
+                        (PAGE_DIGITIZER, USAGE_TIP_SWITCH | USAGE_CONTACT_ID) | (PAGE_GENERIC_DESKTOP, 0x30 | 0x31)
+                    ) {
+                        continue;
+                    }
+                    let index = match (finger, loose) {
+                        (Some((_, slot)), _) => slot,
+                        (None, Some(slot)) => slot,
+                        (None, None) => {
+                            layout.slots.push(Slot::default());
+                            loose = Some(layout.slots.len() - 1);
+                            layout.slots.len() - 1
+                        }
+                    };
+                    let slot = &mut layout.slots[index];
+                    match usage {
+                        (PAGE_DIGITIZER, USAGE_TIP_SWITCH) => slot.tip = Some(field),
+                        (PAGE_DIGITIZER, USAGE_CONTACT_ID) => slot.id = Some(field),
+                        (_, 0x30) => slot.x = Some(field),
+                        _ => slot.y = Some(field),
+                    }
+                }
+                *cursor += bits.saturating_mul(globals.report_count);
+                usages.clear();
+            }
+            0x90 | 0xB0 => usages.clear(),
+            _ => {}
+        }
+    }
+
+    layout.slots.retain(|slot| slot.x.is_some() && slot.y.is_some());
+    (!layout.slots.is_empty()).then_some(layout)
+}
+
+impl TouchLayout {
+    /// Contacts carried by one input report. Slots past the reported
+    /// contact count are unused; in the follow-up reports of hybrid mode
+    /// (count 0) all-zero slots are skipped instead.
+    pub fn decode(&self, report: &[u8]) -> Option<Vec<Contact>> {
+        let (report_id, payload) = if self.uses_report_ids {
+            (*report.first()?, &report[1..])
+        } else {
+            (0, report)
+        };
+        let mut contacts = Vec::new();
+        for (index, slot) in self.slots.iter().enumerate() {
+            let (Some(fx), Some(fy)) = (slot.x, slot.y) else {
+                continue;
+            };
+            if fx.report_id != report_id {
+                continue;
+            }
+            let (Some(x), Some(y)) = (fx.read_normalised(payload), fy.read_normalised(payload)) else {
+                continue;
+            };
+            let tip = match slot.tip {
+                Some(field) => field.read(payload).unwrap_or(0) != 0,
+                None => true,
+            };
+            let id = match slot.id {
+                Some(field) => field.read(payload).unwrap_or(0) as u32,
+                None => index as u32,
+            };
+            contacts.push(Contact { id, tip, x, y });
+        }
+        if contacts.is_empty() {
+            return None;
+        }
+        let count = self
+            .contact_count
+            .filter(|field| field.report_id == report_id)
+            .and_then(|field| field.read(payload));
+        match count {
+            Some(count) if count > 0 => contacts.truncate(count as usize),
+            Some(_) => contacts.retain(|c| c.tip || c.id != 0 || c.x != 0 || c.y != 0),
+            None => {}
+        }
+        Some(contacts)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Calibration
+// ---------------------------------------------------------------------------
+
+/// How the panel is mounted relative to the display.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Orientation {
+    Normal,
+    Left,
+    Right,
+    Inverted,
+}
+
+impl Orientation {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "normal" => Some(Orientation::Normal),
+            "left" => Some(Orientation::Left),
+            "right" => Some(Orientation::Right),
+            "inverted" => Some(Orientation::Inverted),
+            _ => None,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Orientation::Normal => "normal",
+            Orientation::Left => "left",
+            Orientation::Right => "right",
+            Orientation::Inverted => "inverted",
+        }
+    }
+}
+
+/// Screen pixel for a normalised contact on a `width` x `height` display.
+pub fn to_screen(x: i32, y: i32, orientation: Orientation, width: usize, height: usize) -> (i32, i32) {
+    let (x, y) = match orientation {
+        Orientation::Normal => (x, y),
+        Orientation::Left => (y, NORM_MAX - x),
+        Orientation::Right => (NORM_MAX - y, x),
+        Orientation::Inverted => (NORM_MAX - x, NORM_MAX - y),
+    };
+    let sx = x as i64 * (width.max(1) as i64 - 1) / NORM_MAX as i64;
+    let sy = y as i64 * (height.max(1) as i64 - 1) / NORM_MAX as i64;
+    (sx as i32, sy as i32)
+}
+
+// ---------------------------------------------------------------------------
+// Gestures
+// ---------------------------------------------------------------------------
+
+/// Absolute pointer state handed to the desktop.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PointerReport {
+    pub x: i32,
+    pub y: i32,
+    pub left: bool,
+    pub right: bool,
+    pub wheel: i32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Phase {
+    Idle,
+    /// One finger down, not yet a click or a drag.
+    Pending,
+    /// Left button held, following the first finger.
+    Pressed,
+    Scroll {
+        scrolled: bool,
+    },
+}
+
+/// Turns the list of touching points into pointer reports.
+pub struct Gestures {
+    phase: Phase,
+    start: (i32, i32),
+    start_ms: u64,
+    last: (i32, i32),
+    fingers: usize,
+    scroll_y: i32,
+    scroll_acc: i32,
+}
+
+fn centroid_y(points: &[(i32, i32)]) -> i32 {
+    points.iter().map(|p| p.1).sum::<i32>() / points.len().max(1) as i32
+}
+
+impl Gestures {
+    pub const fn new() -> Self {
+        Self {
+            phase: Phase::Idle,
+            start: (0, 0),
+            start_ms: 0,
+            last: (0, 0),
+            fingers: 0,
+            scroll_y: 0,
+            scroll_acc: 0,
+        }
+    }
+
+    fn report(out: &mut VecDeque<PointerReport>, (x, y): (i32, i32), left: bool, right: bool, wheel: i32) {
+        out.push_back(PointerReport {
+            x,
+            y,
+            left,
+            right,
+            wheel,
+        });
+    }
+
+    fn start_scroll(&mut self, points: &[(i32, i32)]) {
+        self.phase = Phase::Scroll { scrolled: false };
+        self.fingers = points.len();
+        self.scroll_y = centroid_y(points);
+        self.scroll_acc = 0;
+    }
+
+    fn press(&mut self, out: &mut VecDeque<PointerReport>) {
+        self.phase = Phase::Pressed;
+        Self::report(out, self.start, true, false, 0);
+    }
+
+    /// New set of touching points, first finger first, in screen pixels.
+    pub fn update(&mut self, points: &[(i32, i32)], now_ms: u64, out: &mut VecDeque<PointerReport>) {
+        match self.phase {
+            Phase::Idle => {
+                let Some(&first) = points.first() else {
+                    return;
+                };
+                self.start = first;
+                self.start_ms = now_ms;
+                self.last = first;
+                Self::report(out, first, false, false, 0);
+                if points.len() >= 2 {
+                    self.start_scroll(points);
+                } else {
+                    self.phase = Phase::Pending;
+                }
+            }
+            Phase::Pending => match points.len() {
+                0 => {
+                    Self::report(out, self.start, true, false, 0);
+                    Self::report(out, self.start, false, false, 0);
+                    self.phase = Phase::Idle;
+                }
+                1 => {
+                    let p = points[0];
+                    self.last = p;
+                    if (p.0 - self.start.0).abs() > TAP_SLOP_PX || (p.1 - self.start.1).abs() > TAP_SLOP_PX {
+                        self.press(out);
+                        Self::report(out, p, true, false, 0);
+                    }
+                }
+                _ => self.start_scroll(points),
+            },
+            Phase::Pressed => match points.first() {
+                Some(&p) => {
+                    if p != self.last {
+                        self.last = p;
+                        Self::report(out, p, true, false, 0);
+                    }
+                }
+                None => {
+                    Self::report(out, self.last, false, false, 0);
+                    self.phase = Phase::Idle;
+                }
+            },
+            Phase::Scroll { scrolled } => {
+                if points.is_empty() {
+                    if !scrolled && now_ms.saturating_sub(self.start_ms) <= TWO_FINGER_TAP_MS {
+                        Self::report(out, self.start, false, true, 0);
+                        Self::report(out, self.start, false, false, 0);
+                    }
+                    self.phase = Phase::Idle;
+                    return;
+                }
+                let y = centroid_y(points);
+                if points.len() != self.fingers {
+                    // A finger joined or left: re-anchor instead of jumping.
+                    self.fingers = points.len();
+                    self.scroll_y = y;
+                    self.scroll_acc = 0;
+                    return;
+                }
+                self.scroll_acc += y - self.scroll_y;
+                self.scroll_y = y;
+                let steps = self.scroll_acc / SCROLL_STEP_PX;
+                if steps != 0 {
+                    self.scroll_acc -= steps * SCROLL_STEP_PX;
+                    self.phase = Phase::Scroll { scrolled: true };
+                    Self::report(out, self.start, false, false, steps);
+                }
+            }
+        }
+    }
+
+    /// Time passing without reports: a resting finger becomes a press.
+    pub fn tick(&mut self, now_ms: u64, out: &mut VecDeque<PointerReport>) {
+        if self.phase == Phase::Pending && now_ms.saturating_sub(self.start_ms) >= PRESS_DELAY_MS {
+            self.press(out);
+            if self.last != self.start {
+                Self::report(out, self.last, true, false, 0);
+            }
+        }
+    }
+}
+
+impl Default for Gestures {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Devices
+// ---------------------------------------------------------------------------
+
+struct Device {
+    proto: usize,
+    endpoint: u8,
+    packet_len: usize,
+    vendor: u16,
+    product: u16,
+    layout: TouchLayout,
+    /// Touching contacts: (id, x, y), normalised, in arrival order.
+    active: Vec<(u32, i32, i32)>,
+    reports: u64,
+    errors: u32,
+}
+
+impl Device {
+    fn apply(&mut self, contacts: &[Contact]) {
+        for contact in contacts.iter() {
+            let pos = self.active.iter().position(|(id, _, _)| *id == contact.id);
+            match (contact.tip, pos) {
+                (true, Some(pos)) => self.active[pos] = (contact.id, contact.x, contact.y),
+                (true, None) if self.active.len() < MAX_CONTACTS => {
+                    self.active.push((contact.id, contact.x, contact.y))
+                }
+                (false, Some(pos)) => {
+                    self.active.remove(pos);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+struct Touch {
+    devices: Vec<Device>,
+    gestures: Gestures,
+    events: VecDeque<PointerReport>,
+    orientation: Orientation,
+    scanned: bool,
+}
+
+static TOUCH: SpinLock<Touch> = SpinLock::new(Touch {
+    devices: Vec::new(),
+    gestures: Gestures::new(),
+    events: VecDeque::new(),
+    orientation: Orientation::Normal,
+    scanned: false,
+});
+
+fn on_orientation_changed(_key: &str, value: Option<&ConfigValue>) {
+    let orientation = match value {
+        Some(ConfigValue::Str(text)) => Orientation::parse(text.as_str()).unwrap_or(Orientation::Normal),
+        _ => Orientation::Normal,
+    };
+    TOUCH.lock().orientation = orientation;
+}
+
+/// Follow `input.touch.orientation` from now on.
+pub fn init() {
+    crate::config::watch(ORIENTATION_KEY, on_orientation_changed);
+}
+
+unsafe fn probe(proto: *mut RawUsbIoProtocol) -> Option<Device> {
+    let mut iface = UsbInterfaceDescriptor::default();
+    if ((*proto).get_interface_descriptor)(proto, &mut iface).is_error() {
+        return None;
+    }
+    // Boot keyboards and mice belong to the firmware drivers.
+    if iface.interface_class != 0x03 || (iface.interface_sub_class == 0x01 && iface.interface_protocol != 0) {
+        return None;
+    }
+    let mut device = UsbDeviceDescriptor::default();
+    let _ = ((*proto).get_device_descriptor)(proto, &mut device);
+    let (endpoint, max_packet) = interrupt_in_endpoint(proto, iface.num_endpoints)?;
+    let desc = read_hid_report_descriptor(proto, iface.interface_number)?;
+    let layout = parse_report_descriptor(&desc)?;
+    hid_set_idle(proto, iface.interface_number);
+    Some(Device {
+        proto: proto as usize,
+        endpoint,
+        packet_len: (max_packet as usize).clamp(1, REPORT_MAX_BYTES),
+        vendor: device.id_vendor,
+        product: device.id_product,
+        layout,
+        active: Vec::new(),
+        reports: 0,
+        errors: 0,
+    })
+}
+
+/// Find USB touch screens. Called when the desktop starts and again after
+/// the firmware may have re-enumerated the bus.
+pub fn reset_uefi() {
+    use uefi::boot::{OpenProtocolAttributes, OpenProtocolParams};
+
+    let handles = uefi::boot::find_handles::<RawUsbIoProtocol>().unwrap_or_default();
+    let agent = uefi::boot::image_handle();
+    let mut devices = Vec::new();
+    for handle in handles.iter() {
+        if devices.len() >= MAX_DEVICES {
+            break;
+        }
+        let params = OpenProtocolParams {
+            handle: *handle,
+            agent,
+            controller: None,
+        };
+        let Ok(scoped) =
+            (unsafe { uefi::boot::open_protocol::<RawUsbIoProtocol>(params, OpenProtocolAttributes::GetProtocol) })
+        else {
+            continue;
+        };
+        let raw = &*scoped as *const RawUsbIoProtocol as *mut RawUsbIoProtocol;
+        if let Some(device) = unsafe { probe(raw) } {
+            core::mem::forget(scoped);
+            crate::klog::log(
+                crate::klog::Level::Info,
+                alloc::format!(
+                    "touch {}: {:04x}:{:04x} {} contactos ep {:#04x}",
+                    devices.len(),
+                    device.vendor,
+                    device.product,
+                    device.layout.slots.len(),
+                    device.endpoint
+                )
+                .as_str(),
+            );
+            devices.push(device);
+        }
+    }
+    on_orientation_changed(ORIENTATION_KEY, crate::config::get(ORIENTATION_KEY).as_ref());
+    let mut touch = TOUCH.lock();
+    touch.devices = devices;
+    touch.gestures = Gestures::new();
+    touch.events.clear();
+    touch.scanned = true;
+}
+
+fn poll_devices(touch: &mut Touch) {
+    let (width, height) = crate::framebuffer::dimensions();
+    let now = crate::timer::now_ms();
+    let Touch {
+        devices,
+        gestures,
+        events,
+        orientation,
+        ..
+    } = touch;
+    for device in devices.iter_mut() {
+        let proto = device.proto as *mut RawUsbIoProtocol;
+        let mut buf = [0u8; REPORT_MAX_BYTES];
+        let mut len = device.packet_len;
+        let mut usb_status = 0u32;
+        let status = unsafe {
+            ((*proto).sync_interrupt_transfer)(
+                proto,
+                device.endpoint,
+                buf.as_mut_ptr() as *mut core::ffi::c_void,
+                &mut len,
+                1,
+                &mut usb_status,
+            )
+        };
+        if status == uefi::Status::TIMEOUT {
+            continue;
+        }
+        if status.is_error() {
+            device.errors = device.errors.saturating_add(1);
+            continue;
+        }
+        let Some(contacts) = device.layout.decode(&buf[..len.min(REPORT_MAX_BYTES)]) else {
+            continue;
+        };
+        device.reports += 1;
+        device.apply(&contacts);
+        let points: Vec<(i32, i32)> = device
+            .active
+            .iter()
+            .map(|(_, x, y)| to_screen(*x, *y, *orientation, width, height))
+            .collect();
+        gestures.update(&points, now, events);
+    }
+    gestures.tick(now, events);
+    while events.len() > EVENT_QUEUE_LIMIT {
+        events.pop_front();
+    }
+}
+
+/// Next pointer report, reading the devices when the queue is empty.
+pub fn poll_event() -> Option<PointerReport> {
+    let mut touch = TOUCH.lock();
+    if touch.devices.is_empty() {
+        return None;
+    }
+    if touch.events.is_empty() {
+        poll_devices(&mut touch);
+    }
+    touch.events.pop_front()
+}
+
+pub fn device_count() -> usize {
+    TOUCH.lock().devices.len()
+}
+
+/// Device list for `touch` and `hwinfo input`.
+pub fn status_lines() -> Vec<String> {
+    let touch = TOUCH.lock();
+    touch
+        .devices
+        .iter()
+        .enumerate()
+        .map(|(i, device)| {
+            alloc::format!(
+                "pantalla tactil {}: {:04x}:{:04x} {} contactos ep {:#04x}, {} informes, {} errores",
+                i,
+                device.vendor,
+                device.product,
+                device.layout.slots.len(),
+                device.endpoint,
+                device.reports,
+                device.errors
+            )
+        })
+        .collect()
+}
+
+/// Shared implementation of `touch [list|rescan|orientation <...>]`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let args = args.trim().to_ascii_lowercase();
+    let (verb, rest) = match args.split_once(' ') {
+        Some((verb, rest)) => (verb, rest.trim()),
+        None => (args.as_str(), ""),
+    };
+    let mut out = Vec::new();
+    match verb {
+        "" | "list" | "status" | "rescan" => {
+            if verb == "rescan" || !TOUCH.lock().scanned {
+                reset_uefi();
+            }
+            let lines = status_lines();
+            if lines.is_empty() {
+                out.push(String::from("No hay pantallas tactiles USB."));
+            }
+            out.extend(lines);
+            out.push(alloc::format!("Orientacion: {}", TOUCH.lock().orientation.name()));
+        }
+        "orientation" => match Orientation::parse(rest) {
+            Some(orientation) => {
+                match crate::config::set(ORIENTATION_KEY, ConfigValue::Str(String::from(orientation.name()))) {
+                    Ok(()) => out.push(alloc::format!("Orientacion: {}", orientation.name())),
+                    Err(e) => out.push(alloc::format!("config: {}", e)),
+                }
+            }
+            None => out.push(String::from("Uso: touch orientation <normal|left|right|inverted>")),
+        },
+        _ => out.push(String::from(
+            "Uso: touch [list|rescan|orientation <normal|left|right|inverted>]",
+        )),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "touch";
+
+    fn descriptor_yields_finger_slots() {
+        // One finger (tip bit, 7 pad bits, id byte, 12-bit X/Y in 16 bits)
+        // and a contact count byte.
+        const DESC: &[u8] = &[
+            0x05, 0x0D, 0x09, 0x04, 0xA1, 0x01, // Touch Screen application
+            0x09, 0x22, 0xA1, 0x02, // Finger
+            0x09, 0x42, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x01, 0x81, 0x02, //
+            0x75, 0x07, 0x95, 0x01, 0x81, 0x03, //
+            0x09, 0x51, 0x25, 0x0F, 0x75, 0x08, 0x95, 0x01, 0x81, 0x02, //
+            0x05, 0x01, 0x26, 0xFF, 0x0F, 0x75, 0x10, 0x95, 0x01, 0x09, 0x30, 0x81, 0x02, 0x09, 0x31, 0x81, 0x02, //
+            0xC0, //
+            0x05, 0x0D, 0x09, 0x54, 0x25, 0x0A, 0x75, 0x08, 0x95, 0x01, 0x81, 0x02, //
+            0xC0,
+        ];
+        let layout = parse_report_descriptor(DESC).ok_or("no es tactil")?;
+        let contacts = layout.decode(&[0x01, 0x03, 0xFF, 0x0F, 0x00, 0x00, 0x01]).ok_or("informe")?;
+        crate::selftest::ensure_eq(
+            contacts.first().copied(),
+            Some(Contact { id: 3, tip: true, x: NORM_MAX, y: 0 }),
+            "contacto",
+        )?;
+        let lifted = layout.decode(&[0x00, 0x03, 0xFF, 0x0F, 0x00, 0x00, 0x01]).ok_or("informe")?;
+        crate::selftest::ensure_eq(lifted.first().map(|c| c.tip), Some(false), "dedo levantado")?;
+        crate::selftest::ensure(
+            parse_report_descriptor(&[0x05, 0x01, 0x09, 0x05, 0xA1, 0x01, 0xC0]).is_none(),
+            "mando",
+        )
+    }
+
+    fn calibration_follows_resolution_and_orientation() {
+        crate::selftest::ensure_eq(to_screen(NORM_MAX, 0, Orientation::Normal, 800, 600), (799, 0), "normal")?;
+        crate::selftest::ensure_eq(to_screen(NORM_MAX, 0, Orientation::Inverted, 800, 600), (0, 599), "invertida")?;
+        crate::selftest::ensure_eq(to_screen(0, 0, Orientation::Left, 1280, 800), (0, 799), "izquierda")?;
+        crate::selftest::ensure_eq(to_screen(0, 0, Orientation::Right, 1280, 800), (1279, 0), "derecha")
+    }
+
+    fn tap_drag_and_two_finger_gestures() {
+        let mut g = Gestures::new();
+        let mut out = VecDeque::new();
+        g.update(&[(100, 100)], 0, &mut out);
+        g.update(&[], 50, &mut out);
+        let buttons: Vec<bool> = out.iter().map(|r| r.left).collect();
+        crate::selftest::ensure_eq(buttons, alloc::vec![false, true, false], "toque = clic")?;
+
+        out.clear();
+        g.update(&[(100, 100)], 1000, &mut out);
+        g.update(&[(140, 100)], 1010, &mut out);
+        g.update(&[], 1020, &mut out);
+        crate::selftest::ensure_eq(out.get(1).map(|r| (r.x, r.left)), Some((100, true)), "pulsa en el origen")?;
+        crate::selftest::ensure_eq(out.get(2).map(|r| (r.x, r.left)), Some((140, true)), "arrastra")?;
+        crate::selftest::ensure_eq(out.back().map(|r| r.left), Some(false), "suelta")?;
+
+        out.clear();
+        g.update(&[(100, 100), (140, 100)], 2000, &mut out);
+        g.update(&[(100, 150), (140, 150)], 2010, &mut out);
+        g.update(&[], 2020, &mut out);
+        crate::selftest::ensure_eq(out.iter().map(|r| r.wheel).sum::<i32>(), 2, "desplaza con dos dedos")?;
+        crate::selftest::ensure(!out.iter().any(|r| r.left || r.right), "sin botones")?;
+
+        out.clear();
+        g.update(&[(10, 10), (30, 10)], 3000, &mut out);
+        g.update(&[], 3100, &mut out);
+        crate::selftest::ensure(out.iter().any(|r| r.right), "toque con dos dedos = clic derecho")?;
+
+        out.clear();
+        g.update(&[(10, 10)], 4000, &mut out);
+        g.tick(4000 + PRESS_DELAY_MS, &mut out);
+        crate::selftest::ensure_eq(out.back().map(|r| r.left), Some(true), "mantener pulsa")
+    }
+}
@@ -0,0 +1,967 @@
+//! TPM 2.0: measured boot and sealing of the data-partition key.
+//!
+//! The TPM is found through the ACPI TPM2 table, or probed at the standard
+//! address, and driven through its memory-mapped registers at locality 0:
+//! the FIFO interface (TIS) or the Command Response Buffer (CRB).
+//!
+//! Firmware has measured itself, the boot manager and the Secure Boot policy
+//! into PCRs 0-7. `init` adds PCR 11, extended with the SHA-256 of the kernel
+//! image as it is on disk, `config` extends PCR 12 with the settings it
+//! loaded and `kmod` extends PCR 13 with every module before it runs.
+//! `measure` keeps a log of all of them for `tpm log`.
+//!
+//! `seal` puts a secret into a TPM object under the owner hierarchy's storage
+//! key. The object has no password; its PolicyPCR policy only lets `unseal`
+//! return the secret while the PCRs in `security.tpm_pcrs` (7 and 11 unless
+//! set) hold the values they had when it was sealed. A disk moved to another
+//! machine, or booted with another kernel or with Secure Boot off, cannot get
+//! it back. `crypt` keeps a copy of the data partition key this way.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use uefi::boot;
+use uefi::fs::FileSystem as UefiFileSystem;
+use uefi::CString16;
+
+use crate::crypto::Sha256;
+use crate::println;
+use crate::spinlock::SpinLock;
+
+/// Locality 0 of a TIS or CRB TPM when ACPI does not say otherwise.
+const TPM_BASE: u64 = 0xFED4_0000;
+
+const TIS_ACCESS: u64 = 0x00;
+const TIS_STS: u64 = 0x18;
+const TIS_FIFO: u64 = 0x24;
+const TIS_INTERFACE_ID: u64 = 0x30;
+const TIS_DID_VID: u64 = 0xF00;
+const ACCESS_VALID: u8 = 0x80;
+const ACCESS_ACTIVE_LOCALITY: u8 = 0x20;
+const ACCESS_REQUEST_USE: u8 = 0x02;
+const STS_VALID: u8 = 0x80;
+const STS_COMMAND_READY: u8 = 0x40;
+const STS_GO: u8 = 0x20;
+const STS_DATA_AVAIL: u8 = 0x10;
+
+const CRB_LOC_CTRL: u64 = 0x08;
+const CRB_LOC_STS: u64 = 0x0C;
+const CRB_INTF_ID_HIGH: u64 = 0x34;
+const CRB_CTRL_REQ: u64 = 0x40;
+const CRB_CTRL_STS: u64 = 0x44;
+const CRB_CTRL_START: u64 = 0x4C;
+const CRB_CMD_SIZE: u64 = 0x58;
+const CRB_CMD_LADDR: u64 = 0x5C;
+const CRB_CMD_HADDR: u64 = 0x60;
+const CRB_RSP_SIZE: u64 = 0x64;
+const CRB_RSP_ADDR: u64 = 0x68;
+const CRB_REQUEST_ACCESS: u32 = 1;
+const CRB_RELINQUISH: u32 = 2;
+const CRB_CMD_READY: u32 = 1;
+const CRB_GO_IDLE: u32 = 2;
+const CRB_FATAL: u32 = 1;
+
+/// TPM2 table start methods this driver handles.
+const START_TIS: u32 = 6;
+const START_CRB: u32 = 7;
+
+const TIMEOUT_ACCESS_US: u64 = 750_000;
+/// Long enough for CreatePrimary on slow discrete parts.
+const TIMEOUT_COMMAND_US: u64 = 5_000_000;
+const POLL_US: u64 = 50;
+
+const HEADER_LEN: usize = 10;
+const RESPONSE_MAX: usize = 4096;
+
+const TAG_NO_SESSIONS: u16 = 0x8001;
+const TAG_SESSIONS: u16 = 0x8002;
+const CC_CREATE_PRIMARY: u32 = 0x131;
+const CC_STARTUP: u32 = 0x144;
+const CC_CREATE: u32 = 0x153;
+const CC_LOAD: u32 = 0x157;
+const CC_UNSEAL: u32 = 0x15E;
+const CC_FLUSH_CONTEXT: u32 = 0x165;
+const CC_START_AUTH_SESSION: u32 = 0x176;
+const CC_GET_RANDOM: u32 = 0x17B;
+const CC_PCR_READ: u32 = 0x17E;
+const CC_POLICY_PCR: u32 = 0x17F;
+const CC_PCR_EXTEND: u32 = 0x182;
+const SU_CLEAR: u16 = 0;
+/// Startup after firmware already sent it.
+const RC_INITIALIZE: u32 = 0x100;
+const RH_OWNER: u32 = 0x4000_0001;
+const RH_NULL: u32 = 0x4000_0007;
+const RS_PW: u32 = 0x4000_0009;
+const ALG_AES: u16 = 0x0006;
+const ALG_KEYEDHASH: u16 = 0x0008;
+const ALG_SHA256: u16 = 0x000B;
+const ALG_NULL: u16 = 0x0010;
+const ALG_ECC: u16 = 0x0023;
+const ALG_CFB: u16 = 0x0043;
+const ECC_NIST_P256: u16 = 0x0003;
+const SE_POLICY: u8 = 0x01;
+/// fixedTPM, fixedParent, sensitiveDataOrigin, userWithAuth, noDA,
+/// restricted, decrypt: the usual storage root key.
+const SRK_ATTRIBUTES: u32 = 0x0003_0472;
+/// fixedTPM, fixedParent, noDA. No userWithAuth: only the policy unseals.
+const SEALED_ATTRIBUTES: u32 = 0x0000_0412;
+
+pub const PCR_KERNEL: u32 = 11;
+pub const PCR_CONFIG: u32 = 12;
+/// Not in the default policy: modules load in no fixed order.
+pub const PCR_MODULES: u32 = 13;
+const PCR_COUNT: u32 = 24;
+const DEFAULT_PCRS: u32 = (1 << 7) | (1 << PCR_KERNEL);
+const PCRS_KEY: &str = "security.tpm_pcrs";
+/// Largest secret a sealed data object holds (MAX_SYM_DATA).
+pub const SEAL_MAX_BYTES: usize = 128;
+/// AES-256-XTS takes two 256-bit keys.
+pub const DATA_KEY_LEN: usize = 64;
+const SEALED_MAGIC: &[u8; 4] = b"RXTS";
+const SEALED_VERSION: u8 = 1;
+/// Used when the loaded image does not report its own path.
+const FALLBACK_IMAGE: &str = "\\EFI\\BOOT\\BOOTX64.EFI";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Interface {
+    Tis,
+    Crb,
+}
+
+#[derive(Clone, Copy)]
+struct Device {
+    base: u64,
+    interface: Interface,
+    /// Device ID in the high half, vendor ID in the low half.
+    id: u32,
+}
+
+#[derive(Clone)]
+struct Event {
+    pcr: u32,
+    what: String,
+    digest: [u8; 32],
+}
+
+static DEVICE: SpinLock<Option<Device>> = SpinLock::new(None);
+static EVENTS: SpinLock<Vec<Event>> = SpinLock::new(Vec::new());
+
+fn wait(timeout_us: u64, ready: impl FnMut() -> bool) -> bool {
+    crate::boottime::wait_until(timeout_us, POLL_US, ready)
+}
+
+impl Device {
+    fn read8(&self, offset: u64) -> u8 {
+        unsafe { core::ptr::read_volatile((self.base + offset) as *const u8) }
+    }
+
+    fn read32(&self, offset: u64) -> u32 {
+        unsafe { core::ptr::read_volatile((self.base + offset) as *const u32) }
+    }
+
+    fn write8(&self, offset: u64, value: u8) {
+        unsafe { core::ptr::write_volatile((self.base + offset) as *mut u8, value) }
+    }
+
+    fn write32(&self, offset: u64, value: u32) {
+        unsafe { core::ptr::write_volatile((self.base + offset) as *mut u32, value) }
+    }
+
+    fn execute(&self, command: &[u8]) -> Result<Vec<u8>, &'static str> {
+        match self.interface {
+            Interface::Tis => self.tis_execute(command),
+            Interface::Crb => self.crb_execute(command),
+        }
+    }
+
+    fn tis_execute(&self, command: &[u8]) -> Result<Vec<u8>, &'static str> {
+        self.write8(TIS_ACCESS, ACCESS_REQUEST_USE);
+        let granted = ACCESS_VALID | ACCESS_ACTIVE_LOCALITY;
+        if !wait(TIMEOUT_ACCESS_US, || self.read8(TIS_ACCESS) & granted == granted) {
+            return Err("localidad 0 no concedida");
+        }
+        let result = self.tis_exchange(command);
+        self.write8(TIS_STS, STS_COMMAND_READY);
+        self.write8(TIS_ACCESS, ACCESS_ACTIVE_LOCALITY);
+        result
+    }
+
+    fn tis_exchange(&self, command: &[u8]) -> Result<Vec<u8>, &'static str> {
+        self.write8(TIS_STS, STS_COMMAND_READY);
+        if !wait(TIMEOUT_ACCESS_US, || self.read8(TIS_STS) & STS_COMMAND_READY != 0) {
+            return Err("el TPM no acepta ordenes");
+        }
+        let mut sent = 0;
+        while sent < command.len() {
+            let burst = self.tis_burst()?.min(command.len() - sent);
+            for &byte in command[sent..sent + burst].iter() {
+                self.write8(TIS_FIFO, byte);
+            }
+            sent += burst;
+        }
+        self.write8(TIS_STS, STS_GO);
+        let done = STS_VALID | STS_DATA_AVAIL;
+        if !wait(TIMEOUT_COMMAND_US, || self.read8(TIS_STS) & done == done) {
+            return Err("el TPM no responde");
+        }
+        let mut response = Vec::with_capacity(HEADER_LEN);
+        self.tis_read(&mut response, HEADER_LEN)?;
+        let len = response_len(&response)?;
+        self.tis_read(&mut response, len)?;
+        Ok(response)
+    }
+
+    /// Bytes the FIFO takes or holds right now.
+    fn tis_burst(&self) -> Result<usize, &'static str> {
+        crate::boottime::wait_for(TIMEOUT_ACCESS_US, POLL_US, || {
+            let burst = ((self.read32(TIS_STS) >> 8) & 0xFFFF) as usize;
+            (burst != 0).then_some(burst)
+        })
+        .ok_or("FIFO del TPM bloqueada")
+    }
+
+    fn tis_read(&self, out: &mut Vec<u8>, len: usize) -> Result<(), &'static str> {
+        while out.len() < len {
+            let burst = self.tis_burst()?.min(len - out.len());
+            for _ in 0..burst {
+                out.push(self.read8(TIS_FIFO));
+            }
+        }
+        Ok(())
+    }
+
+    fn crb_execute(&self, command: &[u8]) -> Result<Vec<u8>, &'static str> {
+        self.write32(CRB_LOC_CTRL, CRB_REQUEST_ACCESS);
+        if !wait(TIMEOUT_ACCESS_US, || self.read32(CRB_LOC_STS) & 1 != 0) {
+            return Err("localidad 0 no concedida");
+        }
+        let result = self.crb_exchange(command);
+        self.write32(CRB_CTRL_REQ, CRB_GO_IDLE);
+        self.write32(CRB_LOC_CTRL, CRB_RELINQUISH);
+        result
+    }
+
+    fn crb_exchange(&self, command: &[u8]) -> Result<Vec<u8>, &'static str> {
+        self.write32(CRB_CTRL_REQ, CRB_CMD_READY);
+        if !wait(TIMEOUT_ACCESS_US, || self.read32(CRB_CTRL_REQ) & CRB_CMD_READY == 0)
+            || self.read32(CRB_CTRL_STS) & CRB_FATAL != 0
+        {
+            return Err("el TPM no acepta ordenes");
+        }
+        let command_addr = ((self.read32(CRB_CMD_HADDR) as u64) << 32) | self.read32(CRB_CMD_LADDR) as u64;
+        let command_size = self.read32(CRB_CMD_SIZE) as usize;
+        let response_addr = ((self.read32(CRB_RSP_ADDR + 4) as u64) << 32) | self.read32(CRB_RSP_ADDR) as u64;
+        let response_size = self.read32(CRB_RSP_SIZE) as usize;
+        if command.len() > command_size || response_size < HEADER_LEN {
+            return Err("buffer CRB demasiado pequeno");
+        }
+        if !crate::paging::kernel_page_mapped(command_addr) || !crate::paging::kernel_page_mapped(response_addr) {
+            return Err("buffer CRB sin mapa");
+        }
+        for (index, &byte) in command.iter().enumerate() {
+            unsafe { core::ptr::write_volatile((command_addr + index as u64) as *mut u8, byte) };
+        }
+        self.write32(CRB_CTRL_START, 1);
+        if !wait(TIMEOUT_COMMAND_US, || self.read32(CRB_CTRL_START) & 1 == 0) {
+            return Err("el TPM no responde");
+        }
+        let read = |index: usize| unsafe { core::ptr::read_volatile((response_addr + index as u64) as *const u8) };
+        let mut response: Vec<u8> = (0..HEADER_LEN).map(read).collect();
+        let len = response_len(&response)?;
+        if len > response_size {
+            return Err("respuesta del TPM truncada");
+        }
+        response.extend((HEADER_LEN..len).map(read));
+        Ok(response)
+    }
+}
+
+fn response_len(header: &[u8]) -> Result<usize, &'static str> {
+    let len = u32::from_be_bytes([header[2], header[3], header[4], header[5]]) as usize;
+    if !(HEADER_LEN..=RESPONSE_MAX).contains(&len) {
+        return Err("respuesta del TPM invalida");
+    }
+    Ok(len)
+}
+
+/// Big-endian TPM marshalling.
+struct Buffer(Vec<u8>);
+
+impl Buffer {
+    fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Command header with the size left to `finish`.
+    fn command(code: u32, sessions: bool) -> Self {
+        let mut buffer = Self::new();
+        buffer.u16(if sessions { TAG_SESSIONS } else { TAG_NO_SESSIONS });
+        buffer.u32(0);
+        buffer.u32(code);
+        buffer
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    /// TPM2B: u16 size, then the bytes.
+    fn sized(&mut self, bytes: &[u8]) {
+        self.u16(bytes.len() as u16);
+        self.bytes(bytes);
+    }
+
+    /// Authorization area with one session that carries no nonce and no
+    /// HMAC: the password session with an empty password, or a policy
+    /// session, which ends with the command.
+    fn auth(&mut self, session: u32) {
+        self.u32(4 + 2 + 1 + 2);
+        self.u32(session);
+        self.u16(0);
+        self.u8(0);
+        self.u16(0);
+    }
+
+    /// TPML_PCR_SELECTION for the SHA-256 bank.
+    fn pcr_selection(&mut self, mask: u32) {
+        self.u32(1);
+        self.u16(ALG_SHA256);
+        self.u8(3);
+        self.bytes(&mask.to_le_bytes()[..3]);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let len = (self.0.len() as u32).to_be_bytes();
+        self.0[2..6].copy_from_slice(&len);
+        self.0
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Parameters of a successful response.
+    fn new(response: &'a [u8]) -> Self {
+        Self {
+            data: response,
+            pos: HEADER_LEN,
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.data.len());
+        let end = end.ok_or_else(|| String::from("tpm: respuesta corta"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn sized(&mut self) -> Result<&'a [u8], String> {
+        let len = self.u16()? as usize;
+        self.take(len)
+    }
+}
+
+fn rc_message(code: u32, rc: u32) -> String {
+    // Format-one TPM_RC_POLICY_FAIL, whatever session it names.
+    if rc & 0x80 != 0 && rc & 0x3F == 0x1D {
+        return String::from("tpm: los PCR no coinciden con los del sellado");
+    }
+    alloc::format!("tpm: la orden 0x{:03x} fallo con 0x{:03x}", code, rc)
+}
+
+/// Send a command and return the response with its return code.
+fn transact(command: &[u8]) -> Result<(u32, Vec<u8>), String> {
+    let device = DEVICE.lock();
+    let device = device.as_ref().ok_or_else(|| String::from("tpm: no hay TPM"))?;
+    let response = device.execute(command).map_err(|err| alloc::format!("tpm: {}", err))?;
+    let rc = u32::from_be_bytes([response[6], response[7], response[8], response[9]]);
+    Ok((rc, response))
+}
+
+fn call(command: Buffer) -> Result<Vec<u8>, String> {
+    let command = command.finish();
+    let code = u32::from_be_bytes([command[6], command[7], command[8], command[9]]);
+    match transact(&command)? {
+        (0, response) => Ok(response),
+        (rc, _) => Err(rc_message(code, rc)),
+    }
+}
+
+fn startup() -> Result<(), String> {
+    let mut command = Buffer::command(CC_STARTUP, false);
+    command.u16(SU_CLEAR);
+    let command = command.finish();
+    match transact(&command)? {
+        (0 | RC_INITIALIZE, _) => Ok(()),
+        (rc, _) => Err(rc_message(CC_STARTUP, rc)),
+    }
+}
+
+fn get_random(len: u16) -> Result<Vec<u8>, String> {
+    let mut command = Buffer::command(CC_GET_RANDOM, false);
+    command.u16(len);
+    let response = call(command)?;
+    Ok(Reader::new(&response).sized()?.to_vec())
+}
+
+fn pcr_extend(pcr: u32, digest: &[u8; 32]) -> Result<(), String> {
+    let mut command = Buffer::command(CC_PCR_EXTEND, true);
+    command.u32(pcr);
+    command.auth(RS_PW);
+    command.u32(1);
+    command.u16(ALG_SHA256);
+    command.bytes(digest);
+    call(command).map(|_| ())
+}
+
+fn pcr_read(pcr: u32) -> Result<[u8; 32], String> {
+    let mut command = Buffer::command(CC_PCR_READ, false);
+    command.pcr_selection(1 << pcr);
+    let response = call(command)?;
+    let mut reader = Reader::new(&response);
+    reader.u32()?;
+    for _ in 0..reader.u32()? {
+        reader.u16()?;
+        let len = reader.u8()? as usize;
+        reader.take(len)?;
+    }
+    if reader.u32()? != 1 {
+        return Err(alloc::format!("tpm: el banco SHA-256 no tiene el PCR {}", pcr));
+    }
+    reader
+        .sized()?
+        .try_into()
+        .map_err(|_| String::from("tpm: PCR de tamano inesperado"))
+}
+
+fn flush(handle: u32) -> Result<(), String> {
+    let mut command = Buffer::command(CC_FLUSH_CONTEXT, false);
+    command.u32(handle);
+    call(command).map(|_| ())
+}
+
+/// Owner storage root key: an ECC P-256 decryption key. Primary keys come
+/// from the hierarchy seed, so every boot recreates the same one.
+fn create_primary() -> Result<u32, String> {
+    let mut public = Buffer::new();
+    public.u16(ALG_ECC);
+    public.u16(ALG_SHA256);
+    public.u32(SRK_ATTRIBUTES);
+    public.sized(&[]);
+    public.u16(ALG_AES);
+    public.u16(128);
+    public.u16(ALG_CFB);
+    public.u16(ALG_NULL);
+    public.u16(ECC_NIST_P256);
+    public.u16(ALG_NULL);
+    public.sized(&[]);
+    public.sized(&[]);
+
+    let mut command = Buffer::command(CC_CREATE_PRIMARY, true);
+    command.u32(RH_OWNER);
+    command.auth(RS_PW);
+    command.sized(&[0, 0, 0, 0]);
+    command.sized(&public.0);
+    command.sized(&[]);
+    command.u32(0);
+    let response = call(command)?;
+    Reader::new(&response).u32()
+}
+
+/// Sealed data object under `parent`: returns its private and public areas.
+fn create_sealed(parent: u32, secret: &[u8], policy: &[u8; 32]) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let mut sensitive = Buffer::new();
+    sensitive.sized(&[]);
+    sensitive.sized(secret);
+    let mut public = Buffer::new();
+    public.u16(ALG_KEYEDHASH);
+    public.u16(ALG_SHA256);
+    public.u32(SEALED_ATTRIBUTES);
+    public.sized(policy);
+    public.u16(ALG_NULL);
+    public.sized(&[]);
+
+    let mut command = Buffer::command(CC_CREATE, true);
+    command.u32(parent);
+    command.auth(RS_PW);
+    command.sized(&sensitive.0);
+    command.sized(&public.0);
+    command.sized(&[]);
+    command.u32(0);
+    let response = call(command)?;
+    let mut reader = Reader::new(&response);
+    reader.u32()?;
+    let private = reader.sized()?.to_vec();
+    let public = reader.sized()?.to_vec();
+    Ok((private, public))
+}
+
+fn load(parent: u32, sealed: &Sealed) -> Result<u32, String> {
+    let mut command = Buffer::command(CC_LOAD, true);
+    command.u32(parent);
+    command.auth(RS_PW);
+    command.sized(&sealed.private);
+    command.sized(&sealed.public);
+    let response = call(command)?;
+    Reader::new(&response).u32()
+}
+
+/// Unbound, unsalted policy session: no session key, so commands that use it
+/// carry no HMAC.
+fn start_policy_session() -> Result<u32, String> {
+    let mut nonce = [0u8; 32];
+    crate::random::fill(&mut nonce);
+    let mut command = Buffer::command(CC_START_AUTH_SESSION, false);
+    command.u32(RH_NULL);
+    command.u32(RH_NULL);
+    command.sized(&nonce);
+    command.sized(&[]);
+    command.u8(SE_POLICY);
+    command.u16(ALG_NULL);
+    command.u16(ALG_SHA256);
+    let response = call(command)?;
+    Reader::new(&response).u32()
+}
+
+/// PolicyPCR against the current values of the PCRs in `mask`.
+fn policy_pcr(session: u32, mask: u32) -> Result<(), String> {
+    let mut command = Buffer::command(CC_POLICY_PCR, false);
+    command.u32(session);
+    command.sized(&[]);
+    command.pcr_selection(mask);
+    call(command).map(|_| ())
+}
+
+fn unseal_object(item: u32, session: u32) -> Result<Vec<u8>, String> {
+    let mut command = Buffer::command(CC_UNSEAL, true);
+    command.u32(item);
+    command.auth(session);
+    let response = call(command)?;
+    let mut reader = Reader::new(&response);
+    reader.u32()?;
+    Ok(reader.sized()?.to_vec())
+}
+
+/// Digest a trial session reaches after PolicyPCR over these PCR values,
+/// given in ascending PCR order.
+fn pcr_policy(mask: u32, values: &[[u8; 32]]) -> [u8; 32] {
+    let mut pcrs = Sha256::new();
+    for value in values.iter() {
+        pcrs.update(value);
+    }
+    let mut selection = Buffer::new();
+    selection.pcr_selection(mask);
+    let mut policy = Sha256::new();
+    policy.update(&[0u8; 32]);
+    policy.update(&CC_POLICY_PCR.to_be_bytes());
+    policy.update(&selection.0);
+    policy.update(&pcrs.finish());
+    policy.finish()
+}
+
+fn pcrs_in(mask: u32) -> impl Iterator<Item = u32> {
+    (0..PCR_COUNT).filter(move |pcr| mask & (1 << pcr) != 0)
+}
+
+/// "7,11" to a PCR mask.
+fn parse_pcrs(text: &str) -> Option<u32> {
+    let mut mask = 0u32;
+    for part in text.split(',') {
+        let pcr = part.trim().parse::<u32>().ok().filter(|&pcr| pcr < PCR_COUNT)?;
+        mask |= 1 << pcr;
+    }
+    (mask != 0).then_some(mask)
+}
+
+fn configured_pcrs() -> u32 {
+    parse_pcrs(crate::config::get_str(PCRS_KEY, "").as_str()).unwrap_or(DEFAULT_PCRS)
+}
+
+/// A secret sealed by `seal`, as stored next to what it protects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sealed {
+    /// PCRs the policy covers.
+    pub pcrs: u32,
+    private: Vec<u8>,
+    public: Vec<u8>,
+}
+
+impl Sealed {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(4 + 1 + 4 + 4 + self.private.len() + self.public.len());
+        out.extend_from_slice(SEALED_MAGIC);
+        out.push(SEALED_VERSION);
+        out.extend_from_slice(&self.pcrs.to_le_bytes());
+        for area in [&self.private, &self.public] {
+            out.extend_from_slice(&(area.len() as u16).to_le_bytes());
+            out.extend_from_slice(area);
+        }
+        out
+    }
+
+    pub fn decode(raw: &[u8]) -> Result<Self, &'static str> {
+        if raw.len() < 9 || &raw[..4] != SEALED_MAGIC || raw[4] != SEALED_VERSION {
+            return Err("tpm: clave sellada no reconocida");
+        }
+        let pcrs = u32::from_le_bytes([raw[5], raw[6], raw[7], raw[8]]);
+        let mut rest = &raw[9..];
+        let mut areas = [Vec::new(), Vec::new()];
+        for area in areas.iter_mut() {
+            if rest.len() < 2 {
+                return Err("tpm: clave sellada truncada");
+            }
+            let len = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+            if rest.len() < 2 + len {
+                return Err("tpm: clave sellada truncada");
+            }
+            area.extend_from_slice(&rest[2..2 + len]);
+            rest = &rest[2 + len..];
+        }
+        let [private, public] = areas;
+        Ok(Self { pcrs, private, public })
+    }
+}
+
+pub fn present() -> bool {
+    DEVICE.lock().is_some()
+}
+
+/// Seal `secret` to the current values of the configured PCRs.
+pub fn seal(secret: &[u8]) -> Result<Sealed, String> {
+    if secret.is_empty() || secret.len() > SEAL_MAX_BYTES {
+        return Err(String::from("tpm: secreto de tamano no valido"));
+    }
+    let pcrs = configured_pcrs();
+    let mut values = Vec::new();
+    for pcr in pcrs_in(pcrs) {
+        values.push(pcr_read(pcr)?);
+    }
+    let policy = pcr_policy(pcrs, &values);
+    let primary = create_primary()?;
+    let created = create_sealed(primary, secret, &policy);
+    let _ = flush(primary);
+    let (private, public) = created?;
+    Ok(Sealed { pcrs, private, public })
+}
+
+/// Get a sealed secret back; fails unless the PCRs still match.
+pub fn unseal(sealed: &Sealed) -> Result<Vec<u8>, String> {
+    let primary = create_primary()?;
+    let item = load(primary, sealed);
+    let _ = flush(primary);
+    let item = item?;
+    let result = start_policy_session().and_then(|session| {
+        let result = policy_pcr(session, sealed.pcrs).and_then(|()| unseal_object(item, session));
+        // A successful Unseal already ended the session.
+        if result.is_err() {
+            let _ = flush(session);
+        }
+        result
+    });
+    let _ = flush(item);
+    result
+}
+
+/// Fresh key for the encrypted data partition, and its sealed copy.
+pub fn new_data_key() -> Result<([u8; DATA_KEY_LEN], Sealed), String> {
+    let mut key = [0u8; DATA_KEY_LEN];
+    crate::random::fill(&mut key);
+    let sealed = seal(&key)?;
+    Ok((key, sealed))
+}
+
+/// Extend `pcr` with the SHA-256 of `data` and log it as `what`. Does nothing
+/// without a TPM.
+pub fn measure(pcr: u32, what: &str, data: &[u8]) {
+    if !present() {
+        return;
+    }
+    let digest = Sha256::digest(data);
+    match pcr_extend(pcr, &digest) {
+        Ok(()) => EVENTS.lock().push(Event {
+            pcr,
+            what: String::from(what),
+            digest,
+        }),
+        Err(err) => crate::klog::log(
+            crate::klog::Level::Warning,
+            alloc::format!("{} ({})", err, what).as_str(),
+        ),
+    }
+}
+
+/// Path of the running image on its volume, from the loaded image protocol.
+fn image_path() -> Option<String> {
+    use uefi::proto::device_path::media::FilePath;
+    use uefi::proto::loaded_image::LoadedImage;
+
+    let loaded = boot::open_protocol_exclusive::<LoadedImage>(boot::image_handle()).ok()?;
+    let mut path = String::new();
+    for node in loaded.file_path()?.node_iter() {
+        if let Ok(file) = <&FilePath>::try_from(node) {
+            let name = file.path_name().to_cstring16().ok()?;
+            if !path.is_empty() && !path.ends_with('\\') {
+                path.push('\\');
+            }
+            let _ = write!(path, "{}", name);
+        }
+    }
+    (!path.is_empty()).then_some(path)
+}
+
+fn kernel_image() -> Option<(String, Vec<u8>)> {
+    let path = image_path().unwrap_or_else(|| String::from(FALLBACK_IMAGE));
+    let name = CString16::try_from(path.as_str()).ok()?;
+    let fs_proto = boot::get_image_file_system(boot::image_handle()).ok()?;
+    let bytes = UefiFileSystem::new(fs_proto).read(&*name).ok()?;
+    Some((path, bytes))
+}
+
+fn detect() -> Option<Device> {
+    let (base, interface) = match crate::acpi::find_table(b"TPM2") {
+        Some(table) => {
+            let len = unsafe { core::ptr::read_unaligned((table + 4) as *const u32) };
+            if len < 52 {
+                return None;
+            }
+            let control = unsafe { core::ptr::read_unaligned((table + 40) as *const u64) };
+            let method = unsafe { core::ptr::read_unaligned((table + 48) as *const u32) };
+            match method {
+                // The control area sits at +0x40 in the register page.
+                START_CRB if control != 0 => (control & !0xFFF, Some(Interface::Crb)),
+                START_TIS => (TPM_BASE, Some(Interface::Tis)),
+                _ => return None,
+            }
+        }
+        None => (TPM_BASE, None),
+    };
+    if !crate::paging::kernel_page_mapped(base) {
+        return None;
+    }
+    let mut device = Device {
+        base,
+        interface: Interface::Tis,
+        id: 0,
+    };
+    let interface_id = device.read32(TIS_INTERFACE_ID);
+    if interface_id == u32::MAX {
+        return None;
+    }
+    device.interface = interface.unwrap_or(if interface_id & 0xF == 1 {
+        Interface::Crb
+    } else {
+        Interface::Tis
+    });
+    device.id = match device.interface {
+        Interface::Tis => device.read32(TIS_DID_VID),
+        Interface::Crb => device.read32(CRB_INTF_ID_HIGH),
+    };
+    if device.id == 0 || device.id == u32::MAX {
+        return None;
+    }
+    Some(device)
+}
+
+/// Find the TPM, start it if firmware did not, feed its RNG to the pool and
+/// measure the kernel.
+pub fn init() {
+    let Some(device) = detect() else {
+        println("TPM: no encontrado");
+        return;
+    };
+    *DEVICE.lock() = Some(device);
+    if let Err(err) = startup() {
+        *DEVICE.lock() = None;
+        println(alloc::format!("TPM: {}", err).as_str());
+        return;
+    }
+    if let Ok(bytes) = get_random(32) {
+        crate::random::add_entropy(&bytes, 128);
+    }
+    match kernel_image() {
+        Some((path, bytes)) => measure(PCR_KERNEL, path.as_str(), &bytes),
+        None => crate::klog::log(
+            crate::klog::Level::Warning,
+            "tpm: imagen del kernel no legible, PCR 11 sin medir",
+        ),
+    }
+    println(
+        alloc::format!(
+            "TPM: 2.0 {} en 0x{:x}, kernel medido en PCR {}",
+            interface_name(device.interface),
+            device.base,
+            PCR_KERNEL
+        )
+        .as_str(),
+    );
+}
+
+fn interface_name(interface: Interface) -> &'static str {
+    match interface {
+        Interface::Tis => "FIFO/TIS",
+        Interface::Crb => "CRB",
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes.iter() {
+        let _ = write!(out, "{:02x}", byte);
+    }
+    out
+}
+
+fn pcr_list(mask: u32) -> String {
+    let pcrs: Vec<String> = pcrs_in(mask).map(|pcr| alloc::format!("{}", pcr)).collect();
+    pcrs.join(",")
+}
+
+pub fn status_lines() -> Vec<String> {
+    let Some(device) = *DEVICE.lock() else {
+        return alloc::vec![String::from(
+            "TPM: no encontrado; el disco de datos no se puede sellar."
+        )];
+    };
+    // Reading the setting may load the configuration, which measures it.
+    let pcrs = configured_pcrs();
+    let measured = EVENTS.lock().len();
+    alloc::vec![
+        alloc::format!(
+            "TPM 2.0 {} en 0x{:x}, fabricante 0x{:04x}, dispositivo 0x{:04x}",
+            interface_name(device.interface),
+            device.base,
+            device.id & 0xFFFF,
+            device.id >> 16
+        ),
+        alloc::format!(
+            "Medidas: {} (kernel en PCR {}, configuracion en PCR {}, modulos en PCR {})",
+            measured,
+            PCR_KERNEL,
+            PCR_CONFIG,
+            PCR_MODULES
+        ),
+        alloc::format!("Sellado con los PCR {} ({})", pcr_list(pcrs), PCRS_KEY),
+    ]
+}
+
+/// `tpm [status]`, `tpm pcrs` shows the SHA-256 bank and `tpm log` what this
+/// boot measured.
+pub fn command_lines(args: &str) -> Vec<String> {
+    match args.trim() {
+        "" | "status" => status_lines(),
+        "pcrs" => {
+            if !present() {
+                return alloc::vec![String::from("TPM: no encontrado")];
+            }
+            let mut out = Vec::new();
+            for pcr in 0..PCR_COUNT {
+                match pcr_read(pcr) {
+                    Ok(value) => out.push(alloc::format!("PCR {:>2}: {}", pcr, hex(&value))),
+                    Err(err) => {
+                        out.push(err);
+                        break;
+                    }
+                }
+            }
+            out
+        }
+        "log" => {
+            let events = EVENTS.lock();
+            if events.is_empty() {
+                return alloc::vec![String::from("Sin medidas en este arranque.")];
+            }
+            events
+                .iter()
+                .map(|event| alloc::format!("PCR {:>2} {} {}", event.pcr, hex(&event.digest), event.what))
+                .collect()
+        }
+        _ => alloc::vec![String::from("Uso: tpm [status|pcrs|log]")],
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "tpm";
+
+    fn command_marshalling() {
+        let mut command = Buffer::command(CC_PCR_EXTEND, true);
+        command.u32(PCR_KERNEL);
+        command.auth(RS_PW);
+        let bytes = command.finish();
+        crate::selftest::ensure_eq(&bytes[..2], [0x80u8, 0x02].as_slice(), "tag")?;
+        crate::selftest::ensure_eq(&bytes[2..6], (bytes.len() as u32).to_be_bytes().as_slice(), "tamano")?;
+        crate::selftest::ensure_eq(&bytes[6..10], [0u8, 0, 0x01, 0x82].as_slice(), "codigo")?;
+        crate::selftest::ensure_eq(&bytes[10..14], [0u8, 0, 0, 11].as_slice(), "handle")?;
+        crate::selftest::ensure_eq(&bytes[14..18], 9u32.to_be_bytes().as_slice(), "tamano de autorizacion")?;
+        crate::selftest::ensure_eq(&bytes[18..22], [0x40u8, 0, 0, 0x09].as_slice(), "sesion de password")
+    }
+
+    fn pcr_selection_bitmap() {
+        let mut selection = Buffer::new();
+        selection.pcr_selection((1 << 7) | (1 << 11) | (1 << 23));
+        crate::selftest::ensure_eq(selection.0.as_slice(), [0u8, 0, 0, 1, 0, 0x0B, 3, 0x80, 0x08, 0x80].as_slice(), "seleccion")
+    }
+
+    fn policy_digest_chains_pcrs() {
+        let values = [[0u8; 32], [0xFFu8; 32]];
+        let policy = pcr_policy(DEFAULT_PCRS, &values);
+        let mut selection = Buffer::new();
+        selection.pcr_selection(DEFAULT_PCRS);
+        let mut pcrs = [0u8; 64];
+        pcrs[32..].fill(0xFF);
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&[0u8; 32]);
+        expected.extend_from_slice(&[0, 0, 0x01, 0x7F]);
+        expected.extend_from_slice(&selection.0);
+        expected.extend_from_slice(&Sha256::digest(&pcrs));
+        crate::selftest::ensure_eq(policy, Sha256::digest(&expected), "politica")?;
+        crate::selftest::ensure(pcr_policy(DEFAULT_PCRS, &[[1u8; 32], [0xFFu8; 32]]) != policy, "PCR ignorado")
+    }
+
+    fn sealed_blob_round_trip() {
+        let sealed = Sealed {
+            pcrs: DEFAULT_PCRS,
+            private: alloc::vec![1, 2, 3],
+            public: alloc::vec![4, 5],
+        };
+        let raw = sealed.encode();
+        crate::selftest::ensure_eq(Sealed::decode(&raw), Ok(sealed), "ida y vuelta")?;
+        crate::selftest::ensure(Sealed::decode(&raw[..raw.len() - 1]).is_err(), "truncada aceptada")
+    }
+
+    fn pcr_lists_parse() {
+        crate::selftest::ensure_eq(parse_pcrs("7, 11"), Some(DEFAULT_PCRS), "lista")?;
+        crate::selftest::ensure_eq(parse_pcrs("24"), None, "fuera de rango")?;
+        crate::selftest::ensure_eq(parse_pcrs(""), None, "vacia")
+    }
+}
@@ -0,0 +1,354 @@
+//! Scoped trace points for offline timing analysis. Begin/end pairs with a
+//! category and a name go into a fixed ring buffer (oldest dropped first)
+//! and `trace save` writes them to the boot volume in the Chrome trace-event
+//! JSON format, which chrome://tracing and ui.perfetto.dev open directly.
+//!
+//! Recording is on from boot so a slow start can be inspected afterwards:
+//! events up to `mark_boot_done` are pinned and only later ones (desktop
+//! frames) wrap around. An event is a timestamp and two static strings, plus
+//! an optional short detail (a URL, a stage) copied inline so nothing
+//! allocates.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::spinlock::SpinLock;
+
+pub const CAPACITY: usize = 16384;
+pub const DETAIL_MAX: usize = 48;
+const TRACE_DIR: &str = "REDUXOS";
+const DEFAULT_FILE: &str = "TRACE.JSN";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    Begin,
+    End,
+    Instant,
+}
+
+impl Kind {
+    const fn phase(self) -> &'static str {
+        match self {
+            Kind::Begin => "B",
+            Kind::End => "E",
+            Kind::Instant => "i",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct TraceEvent {
+    pub ts_us: u64,
+    pub kind: Kind,
+    pub category: &'static str,
+    pub name: &'static str,
+    pub cpu: u16,
+    detail: [u8; DETAIL_MAX],
+    detail_len: u8,
+}
+
+impl TraceEvent {
+    pub fn new(ts_us: u64, kind: Kind, category: &'static str, name: &'static str, cpu: u16, detail: &str) -> Self {
+        let mut event = Self {
+            ts_us,
+            kind,
+            category,
+            name,
+            cpu,
+            detail: [0; DETAIL_MAX],
+            detail_len: 0,
+        };
+        // Cut on a char boundary so the detail stays valid UTF-8.
+        let mut len = detail.len().min(DETAIL_MAX);
+        while !detail.is_char_boundary(len) {
+            len -= 1;
+        }
+        event.detail[..len].copy_from_slice(&detail.as_bytes()[..len]);
+        event.detail_len = len as u8;
+        event
+    }
+
+    pub fn detail(&self) -> &str {
+        core::str::from_utf8(&self.detail[..self.detail_len as usize]).unwrap_or("")
+    }
+}
+
+struct Ring {
+    events: Vec<TraceEvent>,
+    /// Events before this index are never overwritten.
+    pinned: usize,
+    /// Next slot to overwrite once the buffer is full.
+    head: usize,
+    dropped: u64,
+    capacity: usize,
+}
+
+impl Ring {
+    const fn new(capacity: usize) -> Self {
+        Self {
+            events: Vec::new(),
+            pinned: 0,
+            head: 0,
+            dropped: 0,
+            capacity,
+        }
+    }
+
+    fn push(&mut self, event: TraceEvent) {
+        if self.events.len() < self.capacity {
+            if self.events.capacity() == 0 {
+                self.events.reserve_exact(self.capacity);
+            }
+            self.events.push(event);
+            return;
+        }
+        self.events[self.head] = event;
+        self.head += 1;
+        if self.head == self.capacity {
+            self.head = self.pinned;
+        }
+        self.dropped = self.dropped.saturating_add(1);
+    }
+
+    /// Keep what is recorded so far (at most half the buffer) from being
+    /// overwritten.
+    fn pin(&mut self) {
+        self.pinned = self.events.len().min(self.capacity / 2);
+        self.head = self.pinned;
+    }
+
+    /// Events oldest first.
+    fn snapshot(&self) -> Vec<TraceEvent> {
+        let mut out = Vec::with_capacity(self.events.len());
+        out.extend_from_slice(&self.events[..self.pinned]);
+        out.extend_from_slice(&self.events[self.head..]);
+        out.extend_from_slice(&self.events[self.pinned..self.head]);
+        out
+    }
+
+    fn clear(&mut self) {
+        self.events.clear();
+        self.pinned = 0;
+        self.head = 0;
+        self.dropped = 0;
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+static RING: SpinLock<Ring> = SpinLock::new(Ring::new(CAPACITY));
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(on: bool) {
+    ENABLED.store(on, Ordering::Relaxed);
+}
+
+fn record(kind: Kind, category: &'static str, name: &'static str, detail: &str) {
+    if !enabled() {
+        return;
+    }
+    let cpu = crate::smp::current_cpu_index() as u16;
+    let event = TraceEvent::new(crate::perf::now_us(), kind, category, name, cpu, detail);
+    RING.lock().push(event);
+}
+
+pub fn begin(category: &'static str, name: &'static str) {
+    record(Kind::Begin, category, name, "");
+}
+
+pub fn end(category: &'static str, name: &'static str) {
+    record(Kind::End, category, name, "");
+}
+
+pub fn instant(category: &'static str, name: &'static str, detail: &str) {
+    record(Kind::Instant, category, name, detail);
+}
+
+/// Ends its span when dropped.
+pub struct Scope {
+    category: &'static str,
+    name: &'static str,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        end(self.category, self.name);
+    }
+}
+
+/// `let _t = trace::scope("gui", "paint");` traces until the end of the block.
+pub fn scope(category: &'static str, name: &'static str) -> Scope {
+    record(Kind::Begin, category, name, "");
+    Scope { category, name }
+}
+
+/// Like `scope`, with a detail shown in the event's args.
+pub fn scope_with(category: &'static str, name: &'static str, detail: &str) -> Scope {
+    record(Kind::Begin, category, name, detail);
+    Scope { category, name }
+}
+
+/// (recorded events, events overwritten since the last clear)
+pub fn stats() -> (usize, u64) {
+    let ring = RING.lock();
+    (ring.events.len(), ring.dropped)
+}
+
+/// Boot is over: keep its events even when desktop frames fill the buffer.
+pub fn mark_boot_done() {
+    instant("boot", "desktop_ready", "");
+    RING.lock().pin();
+}
+
+pub fn clear() {
+    RING.lock().clear();
+}
+
+pub(crate) fn push_json_str(out: &mut String, text: &str) {
+    out.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(alloc::format!("\\u{:04x}", c as u32).as_str()),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Chrome trace-event JSON (object form) for `events`, oldest first.
+pub fn encode_chrome_json(events: &[TraceEvent]) -> String {
+    let mut out = String::with_capacity(32 + events.len() * 96);
+    out.push_str("{\"traceEvents\":[");
+    for (i, event) in events.iter().enumerate() {
+        if i > 0 {
+            out.push_str(",\n");
+        }
+        out.push_str("{\"name\":");
+        push_json_str(&mut out, event.name);
+        out.push_str(",\"cat\":");
+        push_json_str(&mut out, event.category);
+        out.push_str(
+            alloc::format!(
+                ",\"ph\":\"{}\",\"ts\":{},\"pid\":1,\"tid\":{}",
+                event.kind.phase(),
+                event.ts_us,
+                event.cpu
+            )
+            .as_str(),
+        );
+        if event.kind == Kind::Instant {
+            out.push_str(",\"s\":\"g\"");
+        }
+        if !event.detail().is_empty() {
+            out.push_str(",\"args\":{\"detail\":");
+            push_json_str(&mut out, event.detail());
+            out.push('}');
+        }
+        out.push('}');
+    }
+    out.push_str("],\"displayTimeUnit\":\"ms\"}\n");
+    out
+}
+
+pub fn export_json() -> String {
+    let events = RING.lock().snapshot();
+    encode_chrome_json(events.as_slice())
+}
+
+/// Write the buffer to `\REDUXOS\<file>`; returns the path written.
+pub fn save(file: &str) -> Result<String, &'static str> {
+    let fat = unsafe { crate::fat32::GLOBAL_FAT.get_mut() };
+    if fat.init_status != crate::fat32::InitStatus::Success {
+        return Err("volumen de arranque no montado");
+    }
+    let json = export_json();
+    let dir = fat.ensure_subdirectory(fat.root_cluster, TRACE_DIR)?;
+    fat.write_text_file_in_dir(dir, file, json.as_bytes())?;
+    Ok(alloc::format!("\\{}\\{}", TRACE_DIR, file))
+}
+
+/// Shared implementation of `trace [status|on|off|clear|save [archivo]]`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [] | ["status"] => {
+            let (len, dropped) = stats();
+            out.push(alloc::format!(
+                "Trazas {}: {} eventos de {} ({} descartados)",
+                if enabled() { "activas" } else { "pausadas" },
+                len,
+                CAPACITY,
+                dropped
+            ));
+        }
+        ["on"] | ["off"] => {
+            set_enabled(args.trim() == "on");
+            out.push(alloc::format!(
+                "Trazas {}",
+                if enabled() { "activas" } else { "pausadas" }
+            ));
+        }
+        ["clear"] => {
+            clear();
+            out.push(String::from("Trazas borradas."));
+        }
+        ["save"] | ["save", _] => {
+            let file = args.split_whitespace().nth(1).unwrap_or(DEFAULT_FILE);
+            match save(file) {
+                Ok(path) => {
+                    out.push(alloc::format!("Trazas guardadas en {} ({} eventos).", path, stats().0));
+                    out.push(String::from("Abrelo en chrome://tracing o ui.perfetto.dev."));
+                }
+                Err(e) => out.push(alloc::format!("trace: {}", e)),
+            }
+        }
+        _ => out.push(String::from("Uso: trace [status|on|off|clear|save [archivo]]")),
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "trace";
+
+    fn ring_keeps_newest_in_order() {
+        let mut ring = Ring::new(3);
+        for ts in 1..=5u64 {
+            ring.push(TraceEvent::new(ts, Kind::Instant, "t", "e", 0, ""));
+        }
+        let ts: Vec<u64> = ring.snapshot().iter().map(|e| e.ts_us).collect();
+        crate::selftest::ensure_eq(ts, alloc::vec![3, 4, 5], "mas recientes")?;
+        crate::selftest::ensure_eq(ring.dropped, 2, "descartados")?;
+        ring.clear();
+        crate::selftest::ensure(ring.snapshot().is_empty(), "vacio")?;
+
+        // Pinned boot events survive the wrap.
+        let mut ring = Ring::new(4);
+        ring.push(TraceEvent::new(1, Kind::Instant, "boot", "e", 0, ""));
+        ring.pin();
+        for ts in 2..=6u64 {
+            ring.push(TraceEvent::new(ts, Kind::Instant, "gui", "e", 0, ""));
+        }
+        let ts: Vec<u64> = ring.snapshot().iter().map(|e| e.ts_us).collect();
+        crate::selftest::ensure_eq(ts, alloc::vec![1, 4, 5, 6], "arranque fijado")
+    }
+
+    fn chrome_json_shape_and_escaping() {
+        let long = "ñ".repeat(DETAIL_MAX);
+        crate::selftest::ensure(TraceEvent::new(0, Kind::Begin, "t", "e", 0, long.as_str()).detail().len() <= DETAIL_MAX, "detalle acotado")?;
+        let events = [
+            TraceEvent::new(10, Kind::Begin, "net", "http_get", 1, "http://a/\"b\""),
+            TraceEvent::new(25, Kind::End, "net", "http_get", 1, ""),
+        ];
+        let json = encode_chrome_json(&events);
+        crate::selftest::ensure(json.starts_with("{\"traceEvents\":[{\"name\":\"http_get\",\"cat\":\"net\",\"ph\":\"B\",\"ts\":10,\"pid\":1,\"tid\":1"), "primer evento")?;
+        crate::selftest::ensure(json.contains("\"args\":{\"detail\":\"http://a/\\\"b\\\"\"}"), "comillas escapadas")?;
+        crate::selftest::ensure(json.contains("\"ph\":\"E\",\"ts\":25"), "fin")?;
+        crate::selftest::ensure(json.trim_end().ends_with("\"displayTimeUnit\":\"ms\"}"), "cierre")
+    }
+}
@@ -0,0 +1,586 @@
+//! TrueType outlines for fonts embedded in documents.
+//!
+//! Reads only what drawing text needs: `head` (units per em, `loca` format),
+//! `maxp`, `loca`/`glyf` (simple and composite glyphs), `cmap` (formats 0, 4,
+//! 6 and 12) and `hhea`/`hmtx`. Glyphs come back as move/line/quad commands
+//! in font units with y up; rasterizing them is the caller's job (see
+//! `pdf::raster`). Hinting instructions are ignored.
+
+use alloc::vec::Vec;
+
+/// Composite glyphs nest; deeper than this is treated as broken.
+const MAX_COMPONENT_DEPTH: usize = 4;
+const MAX_GLYPH_POINTS: usize = 8_192;
+
+const FLAG_ON_CURVE: u8 = 0x01;
+const FLAG_X_SHORT: u8 = 0x02;
+const FLAG_Y_SHORT: u8 = 0x04;
+const FLAG_REPEAT: u8 = 0x08;
+const FLAG_X_SAME_OR_POSITIVE: u8 = 0x10;
+const FLAG_Y_SAME_OR_POSITIVE: u8 = 0x20;
+
+const COMPONENT_ARGS_ARE_WORDS: u16 = 0x0001;
+const COMPONENT_ARGS_ARE_XY: u16 = 0x0002;
+const COMPONENT_HAVE_SCALE: u16 = 0x0008;
+const COMPONENT_MORE: u16 = 0x0020;
+const COMPONENT_HAVE_XY_SCALE: u16 = 0x0040;
+const COMPONENT_HAVE_2X2: u16 = 0x0080;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathOp {
+    Move(i32, i32),
+    Line(i32, i32),
+    /// Control point, then end point.
+    Quad(i32, i32, i32, i32),
+    Close,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct OutlinePoint {
+    x: i32,
+    y: i32,
+    on_curve: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Table {
+    offset: usize,
+    len: usize,
+}
+
+pub struct Font {
+    data: Vec<u8>,
+    units_per_em: u16,
+    long_loca: bool,
+    num_glyphs: u16,
+    loca: Table,
+    glyf: Table,
+    hmtx: Option<Table>,
+    num_h_metrics: u16,
+    /// `cmap` subtables as (platform, encoding, absolute offset).
+    cmaps: Vec<(u16, u16, usize)>,
+}
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_i16(data: &[u8], at: usize) -> Option<i16> {
+    read_u16(data, at).map(|v| v as i16)
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+impl Font {
+    /// Parse an sfnt with TrueType outlines. CFF-flavoured OpenType (`OTTO`)
+    /// and collections are rejected.
+    pub fn parse(data: Vec<u8>) -> Option<Font> {
+        let version = read_u32(&data, 0)?;
+        if version != 0x0001_0000 && version != u32::from_be_bytes(*b"true") {
+            return None;
+        }
+        let num_tables = read_u16(&data, 4)? as usize;
+        let table = |tag: &[u8; 4]| -> Option<Table> {
+            (0..num_tables).find_map(|i| {
+                let record = 12 + i * 16;
+                if data.get(record..record + 4)? != tag {
+                    return None;
+                }
+                let offset = read_u32(&data, record + 8)? as usize;
+                let len = read_u32(&data, record + 12)? as usize;
+                data.get(offset..offset.checked_add(len)?)?;
+                Some(Table { offset, len })
+            })
+        };
+        let head = table(b"head")?;
+        let maxp = table(b"maxp")?;
+        let loca = table(b"loca")?;
+        let glyf = table(b"glyf")?;
+        let units_per_em = read_u16(&data, head.offset + 18)?;
+        let long_loca = read_i16(&data, head.offset + 50)? != 0;
+        let num_glyphs = read_u16(&data, maxp.offset + 4)?;
+        let num_h_metrics = table(b"hhea")
+            .and_then(|hhea| read_u16(&data, hhea.offset + 34))
+            .unwrap_or(0);
+        let hmtx = table(b"hmtx");
+        let mut cmaps = Vec::new();
+        if let Some(cmap) = table(b"cmap") {
+            let count = read_u16(&data, cmap.offset + 2).unwrap_or(0) as usize;
+            for i in 0..count {
+                let record = cmap.offset + 4 + i * 8;
+                let (Some(platform), Some(encoding), Some(offset)) = (
+                    read_u16(&data, record),
+                    read_u16(&data, record + 2),
+                    read_u32(&data, record + 4),
+                ) else {
+                    break;
+                };
+                let at = cmap.offset + offset as usize;
+                if at < cmap.offset + cmap.len {
+                    cmaps.push((platform, encoding, at));
+                }
+            }
+        }
+        if units_per_em == 0 {
+            return None;
+        }
+        Some(Font {
+            data,
+            units_per_em,
+            long_loca,
+            num_glyphs,
+            loca,
+            glyf,
+            hmtx,
+            num_h_metrics,
+            cmaps,
+        })
+    }
+
+    pub fn units_per_em(&self) -> u16 {
+        self.units_per_em
+    }
+
+    pub fn num_glyphs(&self) -> u16 {
+        self.num_glyphs
+    }
+
+    /// Whether the font has a Unicode `cmap` (Windows or Unicode platform).
+    pub fn has_unicode_cmap(&self) -> bool {
+        self.cmaps
+            .iter()
+            .any(|(p, e, _)| *p == 0 || (*p == 3 && (*e == 1 || *e == 10)))
+    }
+
+    /// Glyph for a Unicode scalar, 0 (`.notdef`) when the font lacks it.
+    pub fn glyph_index(&self, ch: char) -> u16 {
+        self.cmaps
+            .iter()
+            .filter(|(p, e, _)| *p == 0 || (*p == 3 && (*e == 1 || *e == 10)))
+            .find_map(|(_, _, at)| self.cmap_lookup(*at, ch as u32))
+            .unwrap_or(0)
+    }
+
+    /// Glyph for a byte code of a symbolic font: the (3,0) subtable at
+    /// 0xF000 + code or code, then the Macintosh (1,0) one.
+    pub fn symbol_glyph_index(&self, code: u8) -> u16 {
+        let find = |platform: u16, encoding: u16, code: u32| {
+            self.cmaps
+                .iter()
+                .filter(|(p, e, _)| *p == platform && *e == encoding)
+                .find_map(|(_, _, at)| self.cmap_lookup(*at, code))
+        };
+        find(3, 0, 0xF000 + code as u32)
+            .or_else(|| find(3, 0, code as u32))
+            .or_else(|| find(1, 0, code as u32))
+            .unwrap_or(0)
+    }
+
+    fn cmap_lookup(&self, at: usize, code: u32) -> Option<u16> {
+        let data = self.data.as_slice();
+        let glyph = match read_u16(data, at)? {
+            0 => *data.get(at + 6 + code as usize).filter(|_| code < 256)? as u16,
+            4 => {
+                if code > 0xFFFF {
+                    return None;
+                }
+                let seg_count = read_u16(data, at + 6)? as usize / 2;
+                let ends = at + 14;
+                let starts = ends + seg_count * 2 + 2;
+                let deltas = starts + seg_count * 2;
+                let range_offsets = deltas + seg_count * 2;
+                let seg =
+                    (0..seg_count).find(|i| read_u16(data, ends + i * 2).is_some_and(|end| end as u32 >= code))?;
+                let start = read_u16(data, starts + seg * 2)? as u32;
+                if code < start {
+                    return None;
+                }
+                let delta = read_u16(data, deltas + seg * 2)?;
+                let range_offset = read_u16(data, range_offsets + seg * 2)? as usize;
+                if range_offset == 0 {
+                    (code as u16).wrapping_add(delta)
+                } else {
+                    let addr = range_offsets + seg * 2 + range_offset + (code - start) as usize * 2;
+                    match read_u16(data, addr)? {
+                        0 => 0,
+                        g => g.wrapping_add(delta),
+                    }
+                }
+            }
+            6 => {
+                let first = read_u16(data, at + 6)? as u32;
+                let count = read_u16(data, at + 8)? as u32;
+                if code < first || code >= first + count {
+                    return None;
+                }
+                read_u16(data, at + 10 + (code - first) as usize * 2)?
+            }
+            12 => {
+                let groups = read_u32(data, at + 12)? as usize;
+                (0..groups).find_map(|i| {
+                    let group = at + 16 + i * 12;
+                    let first = read_u32(data, group)?;
+                    let last = read_u32(data, group + 4)?;
+                    if code < first || code > last {
+                        return None;
+                    }
+                    Some((read_u32(data, group + 8)? + (code - first)) as u16)
+                })?
+            }
+            _ => return None,
+        };
+        if glyph == 0 || glyph >= self.num_glyphs {
+            None
+        } else {
+            Some(glyph)
+        }
+    }
+
+    /// Advance width in font units.
+    pub fn advance_width(&self, glyph: u16) -> u16 {
+        let Some(hmtx) = self.hmtx else {
+            return self.units_per_em / 2;
+        };
+        if self.num_h_metrics == 0 {
+            return self.units_per_em / 2;
+        }
+        let index = glyph.min(self.num_h_metrics - 1) as usize;
+        if index * 4 + 2 > hmtx.len {
+            return self.units_per_em / 2;
+        }
+        read_u16(&self.data, hmtx.offset + index * 4).unwrap_or(self.units_per_em / 2)
+    }
+
+    fn glyph_range(&self, glyph: u16) -> Option<(usize, usize)> {
+        if glyph >= self.num_glyphs {
+            return None;
+        }
+        let (start, end) = if self.long_loca {
+            let at = self.loca.offset + glyph as usize * 4;
+            (
+                read_u32(&self.data, at)? as usize,
+                read_u32(&self.data, at + 4)? as usize,
+            )
+        } else {
+            let at = self.loca.offset + glyph as usize * 2;
+            (
+                read_u16(&self.data, at)? as usize * 2,
+                read_u16(&self.data, at + 2)? as usize * 2,
+            )
+        };
+        if start >= end || end > self.glyf.len {
+            return None;
+        }
+        Some((self.glyf.offset + start, self.glyf.offset + end))
+    }
+
+    /// Outline of `glyph` in font units. Empty for blanks and bad data.
+    pub fn glyph_path(&self, glyph: u16) -> Vec<PathOp> {
+        let mut contours = Vec::new();
+        self.collect_contours(glyph, 0, &mut contours);
+        let mut path = Vec::new();
+        for contour in contours.iter() {
+            contour_path(contour, &mut path);
+        }
+        path
+    }
+
+    fn collect_contours(&self, glyph: u16, depth: usize, out: &mut Vec<Vec<OutlinePoint>>) -> Option<()> {
+        let (start, end) = self.glyph_range(glyph)?;
+        let data = self.data.get(start..end)?;
+        let contours = read_i16(data, 0)?;
+        if contours >= 0 {
+            return simple_glyph(data, contours as usize, out);
+        }
+        if depth >= MAX_COMPONENT_DEPTH {
+            return None;
+        }
+        let mut at = 10usize;
+        loop {
+            let flags = read_u16(data, at)?;
+            let component = read_u16(data, at + 2)?;
+            at += 4;
+            let (arg1, arg2) = if flags & COMPONENT_ARGS_ARE_WORDS != 0 {
+                at += 4;
+                (read_i16(data, at - 4)? as i32, read_i16(data, at - 2)? as i32)
+            } else {
+                at += 2;
+                (*data.get(at - 2)? as i8 as i32, *data.get(at - 1)? as i8 as i32)
+            };
+            // Scales are F2Dot14: 1 << 14 is 1.0.
+            let (mut a, mut b, mut c, mut d) = (1 << 14, 0, 0, 1 << 14);
+            if flags & COMPONENT_HAVE_SCALE != 0 {
+                a = read_i16(data, at)? as i32;
+                d = a;
+                at += 2;
+            } else if flags & COMPONENT_HAVE_XY_SCALE != 0 {
+                a = read_i16(data, at)? as i32;
+                d = read_i16(data, at + 2)? as i32;
+                at += 4;
+            } else if flags & COMPONENT_HAVE_2X2 != 0 {
+                a = read_i16(data, at)? as i32;
+                b = read_i16(data, at + 2)? as i32;
+                c = read_i16(data, at + 4)? as i32;
+                d = read_i16(data, at + 6)? as i32;
+                at += 8;
+            }
+            // Point-matched placement is rare; treat it as no offset.
+            let (dx, dy) = if flags & COMPONENT_ARGS_ARE_XY != 0 {
+                (arg1, arg2)
+            } else {
+                (0, 0)
+            };
+            let first = out.len();
+            self.collect_contours(component, depth + 1, out);
+            for contour in out[first..].iter_mut() {
+                for p in contour.iter_mut() {
+                    let (x, y) = (p.x, p.y);
+                    p.x = ((x * a + y * c) >> 14) + dx;
+                    p.y = ((x * b + y * d) >> 14) + dy;
+                }
+            }
+            if flags & COMPONENT_MORE == 0 {
+                return Some(());
+            }
+        }
+    }
+}
+
+fn simple_glyph(data: &[u8], contours: usize, out: &mut Vec<Vec<OutlinePoint>>) -> Option<()> {
+    let mut ends = Vec::with_capacity(contours);
+    for i in 0..contours {
+        ends.push(read_u16(data, 10 + i * 2)? as usize);
+    }
+    let points = ends.last().map(|e| e + 1).unwrap_or(0);
+    if points > MAX_GLYPH_POINTS {
+        return None;
+    }
+    let instructions = read_u16(data, 10 + contours * 2)? as usize;
+    let mut at = 12 + contours * 2 + instructions;
+
+    let mut flags = Vec::with_capacity(points);
+    while flags.len() < points {
+        let flag = *data.get(at)?;
+        at += 1;
+        flags.push(flag);
+        if flag & FLAG_REPEAT != 0 {
+            let repeat = *data.get(at)?;
+            at += 1;
+            for _ in 0..repeat {
+                flags.push(flag);
+            }
+        }
+    }
+    flags.truncate(points);
+
+    let mut read_coords = |short: u8, same_or_positive: u8| -> Option<Vec<i32>> {
+        let mut values = Vec::with_capacity(points);
+        let mut value = 0i32;
+        for flag in flags.iter() {
+            if flag & short != 0 {
+                let delta = *data.get(at)? as i32;
+                at += 1;
+                value += if flag & same_or_positive != 0 { delta } else { -delta };
+            } else if flag & same_or_positive == 0 {
+                value += read_i16(data, at)? as i32;
+                at += 2;
+            }
+            values.push(value);
+        }
+        Some(values)
+    };
+    let xs = read_coords(FLAG_X_SHORT, FLAG_X_SAME_OR_POSITIVE)?;
+    let ys = read_coords(FLAG_Y_SHORT, FLAG_Y_SAME_OR_POSITIVE)?;
+
+    let mut start = 0usize;
+    for end in ends {
+        if end < start || end >= points {
+            return None;
+        }
+        out.push(
+            (start..=end)
+                .map(|i| OutlinePoint {
+                    x: xs[i],
+                    y: ys[i],
+                    on_curve: flags[i] & FLAG_ON_CURVE != 0,
+                })
+                .collect(),
+        );
+        start = end + 1;
+    }
+    Some(())
+}
+
+/// One closed contour as path commands. Two off-curve points in a row
+/// imply an on-curve point halfway between them.
+fn contour_path(contour: &[OutlinePoint], path: &mut Vec<PathOp>) {
+    let n = contour.len();
+    if n < 2 {
+        return;
+    }
+    let mid = |a: OutlinePoint, b: OutlinePoint| ((a.x + b.x) / 2, (a.y + b.y) / 2);
+    // Start on an on-curve point, or the midpoint of the first two.
+    let first_on = contour.iter().position(|p| p.on_curve);
+    let (start, offset) = match first_on {
+        Some(i) => ((contour[i].x, contour[i].y), i),
+        None => (mid(contour[0], contour[1]), 0),
+    };
+    path.push(PathOp::Move(start.0, start.1));
+    let mut control: Option<(i32, i32)> = None;
+    for step in 1..=n {
+        let p = contour[(offset + step) % n];
+        match (p.on_curve, control) {
+            (true, None) => path.push(PathOp::Line(p.x, p.y)),
+            (true, Some((cx, cy))) => {
+                path.push(PathOp::Quad(cx, cy, p.x, p.y));
+                control = None;
+            }
+            (false, None) => control = Some((p.x, p.y)),
+            (false, Some((cx, cy))) => {
+                let (mx, my) = ((cx + p.x) / 2, (cy + p.y) / 2);
+                path.push(PathOp::Quad(cx, cy, mx, my));
+                control = Some((p.x, p.y));
+            }
+        }
+    }
+    if let Some((cx, cy)) = control {
+        path.push(PathOp::Quad(cx, cy, start.0, start.1));
+    }
+    path.push(PathOp::Close);
+}
+
+crate::selftest::kernel_tests! {
+    "ttf";
+
+    fn square_glyph_through_cmap_and_composite() {
+        let font = Font::parse(test_font()).ok_or("fuente")?;
+        crate::selftest::ensure_eq(font.units_per_em(), 1000, "unidades")?;
+        crate::selftest::ensure(font.has_unicode_cmap(), "cmap unicode")?;
+        crate::selftest::ensure_eq(font.glyph_index('A'), 1, "A")?;
+        crate::selftest::ensure_eq(font.glyph_index('B'), 2, "B")?;
+        crate::selftest::ensure_eq(font.glyph_index('z'), 0, "sin glifo")?;
+        crate::selftest::ensure_eq(font.advance_width(1), 600, "avance")?;
+        crate::selftest::ensure_eq(
+            font.glyph_path(1),
+            alloc::vec![
+                PathOp::Move(100, 0),
+                PathOp::Line(500, 0),
+                PathOp::Line(500, 700),
+                PathOp::Line(100, 700),
+                PathOp::Line(100, 0),
+                PathOp::Close,
+            ],
+            "cuadrado",
+        )?;
+        let moved = font.glyph_path(2);
+        crate::selftest::ensure_eq(moved.first().copied(), Some(PathOp::Move(150, 20)), "compuesto desplazado")
+    }
+}
+
+/// A font with `.notdef`, a square "A" (one contour, short and word
+/// coordinates) and a "B" that is "A" moved by (50, 20).
+#[cfg(feature = "selftest")]
+fn test_font() -> Vec<u8> {
+    let mut square = Vec::new();
+    for v in [1i16, 100, 0, 500, 700, 3, 0] {
+        square.extend_from_slice(&v.to_be_bytes());
+    }
+    // Flags: two repeated "word x, same y" points, "same x, word y", then
+    // one more "word x, same y".
+    square.extend_from_slice(&[0x21 | FLAG_REPEAT, 1, 0x11, 0x21]);
+    for v in [100i16, 400, -400, 700] {
+        square.extend_from_slice(&v.to_be_bytes());
+    }
+    let mut composite = Vec::new();
+    for v in [-1i16, 150, 20, 550, 720] {
+        composite.extend_from_slice(&v.to_be_bytes());
+    }
+    composite.extend_from_slice(&(COMPONENT_ARGS_ARE_XY).to_be_bytes());
+    composite.extend_from_slice(&1u16.to_be_bytes());
+    composite.extend_from_slice(&[50, 20]);
+
+    let mut glyf = square;
+    while glyf.len() % 2 != 0 {
+        glyf.push(0);
+    }
+    let b_at = glyf.len();
+    glyf.extend_from_slice(&composite);
+    while glyf.len() % 2 != 0 {
+        glyf.push(0);
+    }
+    let mut loca = Vec::new();
+    for v in [0usize, 0, b_at, glyf.len()] {
+        loca.extend_from_slice(&((v / 2) as u16).to_be_bytes());
+    }
+
+    let mut head = alloc::vec![0u8; 54];
+    head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+    let mut maxp = alloc::vec![0u8; 6];
+    maxp[4..6].copy_from_slice(&3u16.to_be_bytes());
+    let mut hhea = alloc::vec![0u8; 36];
+    hhea[34..36].copy_from_slice(&3u16.to_be_bytes());
+    let mut hmtx = Vec::new();
+    for (advance, lsb) in [(500u16, 0i16), (600, 100), (650, 150)] {
+        hmtx.extend_from_slice(&advance.to_be_bytes());
+        hmtx.extend_from_slice(&lsb.to_be_bytes());
+    }
+    // Format 4 with segments 'A'..'B' (delta -64) and the 0xFFFF end.
+    let mut subtable = Vec::new();
+    for v in [
+        4u16,
+        32,
+        0,
+        4,
+        4,
+        1,
+        0,
+        0x42,
+        0xFFFF,
+        0,
+        0x41,
+        0xFFFF,
+        (-64i16) as u16,
+        1,
+        0,
+        0,
+    ] {
+        subtable.extend_from_slice(&v.to_be_bytes());
+    }
+    let mut cmap = Vec::new();
+    for v in [0u16, 1, 3, 1] {
+        cmap.extend_from_slice(&v.to_be_bytes());
+    }
+    cmap.extend_from_slice(&12u32.to_be_bytes());
+    cmap.extend_from_slice(&subtable);
+
+    let tables: [(&[u8; 4], Vec<u8>); 7] = [
+        (b"cmap", cmap),
+        (b"glyf", glyf),
+        (b"head", head),
+        (b"hhea", hhea),
+        (b"hmtx", hmtx),
+        (b"loca", loca),
+        (b"maxp", maxp),
+    ];
+    let mut font = Vec::new();
+    font.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    font.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+    font.extend_from_slice(&[0; 6]);
+    let mut offset = 12 + tables.len() * 16;
+    for (tag, body) in tables.iter() {
+        font.extend_from_slice(*tag);
+        font.extend_from_slice(&0u32.to_be_bytes());
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        offset += (body.len() + 3) & !3;
+    }
+    for (_, body) in tables.iter() {
+        font.extend_from_slice(body);
+        while font.len() % 4 != 0 {
+            font.push(0);
+        }
+    }
+    font
+}
@@ -0,0 +1,376 @@
+//! User memory access: SMEP, SMAP and UMIP, and the helpers syscalls use
+//! to touch user pointers.
+//!
+//! With SMAP on, the kernel faults on any page user mode can reach unless
+//! RFLAGS.AC is set. Syscalls therefore read and write user memory only
+//! through `copy_from_user`, `copy_to_user`, `read_user`, `write_user`,
+//! `clear_user` and `copy_cstr_from_user`, which check the range, open the
+//! window with STAC and close it with CLAC. SMEP keeps the kernel from
+//! running user pages and UMIP keeps SGDT/SIDT/SLDT/SMSW/STR out of ring 3.
+//!
+//! The range check refuses null pointers and ranges that wrap or leave the
+//! lower half. Guest memory shares the kernel's identity map, so that alone
+//! cannot tell guest pages from kernel ones. While a Linux guest syscall runs,
+//! `GuestScope` also holds the ranges its process has mapped (mmap slots,
+//! heap, loaded images and stack) and every range must lie inside them.
+//! Other callers get only the lower-half check.
+//!
+//! The copies themselves are `rep movsb`/`rep stosb` in `uaccess_copy` and
+//! `uaccess_clear`. A page fault on either instruction does not stop the
+//! machine: the fault stub in interrupts.rs resumes after it and the helper
+//! returns `Fault` for the bytes it could not move.
+
+use core::arch::x86_64::{__cpuid, __cpuid_count};
+use core::arch::{asm, global_asm};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use crate::println;
+use crate::spinlock::SpinLock;
+
+const CR4_UMIP: u64 = 1 << 11;
+const CR4_SMEP: u64 = 1 << 20;
+const CR4_SMAP: u64 = 1 << 21;
+/// First address above the canonical lower half.
+const USER_TOP: u64 = 0x0000_8000_0000_0000;
+
+/// CR4 bits the BSP turned on; APs copy them.
+static CR4_BITS: AtomicU64 = AtomicU64::new(0);
+static SMAP_ON: AtomicBool = AtomicBool::new(false);
+
+/// Most ranges one guest call is checked against: every mmap slot plus the
+/// heap, the images and the stack.
+const MAX_GUEST_REGIONS: usize = 80;
+
+/// A user range the kernel refused to touch (EFAULT).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fault;
+
+/// The ranges a Linux guest process has mapped.
+#[derive(Clone, Copy)]
+pub struct GuestRegions {
+    ranges: [(u64, u64); MAX_GUEST_REGIONS],
+    count: usize,
+}
+
+impl GuestRegions {
+    pub const fn new() -> Self {
+        Self {
+            ranges: [(0, 0); MAX_GUEST_REGIONS],
+            count: 0,
+        }
+    }
+
+    /// Add `[start, start + len)`. Empty and wrapping ranges are skipped.
+    pub fn push(&mut self, start: u64, len: u64) {
+        let Some(end) = start.checked_add(len) else {
+            return;
+        };
+        if len != 0 && self.count < MAX_GUEST_REGIONS {
+            self.ranges[self.count] = (start, end);
+            self.count += 1;
+        }
+    }
+
+    /// Whether `[addr, end)` is mapped, possibly across adjacent ranges.
+    fn covers(&self, addr: u64, end: u64) -> bool {
+        let mut cursor = addr;
+        while cursor < end {
+            let ranges = &self.ranges[..self.count];
+            let Some(&(_, stop)) = ranges.iter().find(|&&(start, stop)| start <= cursor && cursor < stop) else {
+                return false;
+            };
+            cursor = stop;
+        }
+        true
+    }
+}
+
+/// Set by `GuestScope`. The Linux shim runs on one CPU at a time.
+static GUEST: SpinLock<Option<GuestRegions>> = SpinLock::new(None);
+
+/// Holds a guest's regions for `check` until dropped.
+pub struct GuestScope;
+
+impl GuestScope {
+    pub fn enter(regions: GuestRegions) -> Self {
+        *GUEST.lock() = Some(regions);
+        GuestScope
+    }
+}
+
+impl Drop for GuestScope {
+    fn drop(&mut self) {
+        *GUEST.lock() = None;
+    }
+}
+
+global_asm!(
+    r#"
+// uaccess_copy(dst: rdi, src: rsi, len: rdx) -> bytes not copied.
+// uaccess_clear(dst: rdi, len: rsi) -> bytes not cleared.
+// A #PF on the *_insn label resumes at *_done with rcx still holding the
+// count left. The fault stubs load the vector into r15 before saving it,
+// so r15 is kept here instead of in the frame.
+.global uaccess_copy
+.global uaccess_copy_insn
+.global uaccess_copy_done
+uaccess_copy:
+    push r15
+    mov rcx, rdx
+uaccess_copy_insn:
+    rep movsb
+uaccess_copy_done:
+    pop r15
+    mov rax, rcx
+    ret
+
+.global uaccess_clear
+.global uaccess_clear_insn
+.global uaccess_clear_done
+uaccess_clear:
+    push r15
+    mov rcx, rsi
+    xor eax, eax
+uaccess_clear_insn:
+    rep stosb
+uaccess_clear_done:
+    pop r15
+    mov rax, rcx
+    ret
+"#
+);
+
+unsafe extern "C" {
+    fn uaccess_copy(dst: *mut u8, src: *const u8, len: usize) -> usize;
+    fn uaccess_clear(dst: *mut u8, len: usize) -> usize;
+}
+
+fn supported() -> u64 {
+    if unsafe { __cpuid(0) }.eax < 7 {
+        return 0;
+    }
+    let leaf7 = unsafe { __cpuid_count(7, 0) };
+    let mut bits = 0;
+    if leaf7.ebx & (1 << 7) != 0 {
+        bits |= CR4_SMEP;
+    }
+    if leaf7.ebx & (1 << 20) != 0 {
+        bits |= CR4_SMAP;
+    }
+    if leaf7.ecx & (1 << 2) != 0 {
+        bits |= CR4_UMIP;
+    }
+    bits
+}
+
+fn read_cr4() -> u64 {
+    let cr4: u64;
+    unsafe { asm!("mov {}, cr4", out(reg) cr4, options(nomem, nostack, preserves_flags)) };
+    cr4
+}
+
+/// Turn on this CPU the CR4 bits the BSP enabled.
+pub fn enable_on_this_cpu() {
+    let bits = CR4_BITS.load(Ordering::Acquire);
+    if bits != 0 {
+        unsafe { asm!("mov cr4, {}", in(reg) read_cr4() | bits, options(nostack, preserves_flags)) };
+    }
+}
+
+extern "efiapi" fn prepare_ap(_arg: *mut core::ffi::c_void) {
+    enable_on_this_cpu();
+}
+
+pub fn smep_enabled() -> bool {
+    read_cr4() & CR4_SMEP != 0
+}
+
+pub fn smap_enabled() -> bool {
+    SMAP_ON.load(Ordering::Acquire)
+}
+
+pub fn umip_enabled() -> bool {
+    read_cr4() & CR4_UMIP != 0
+}
+
+/// Enable what the CPU supports on the BSP and the firmware-run APs. A
+/// firmware map that lets user mode reach kernel code or data would fault at
+/// once, so SMEP and SMAP stay off if it does.
+pub fn init() {
+    let mut bits = supported();
+    let code = init as *const () as u64;
+    let marker = 0u8;
+    let stack = &marker as *const u8 as u64;
+    let data = core::ptr::addr_of!(CR4_BITS) as u64;
+    let heap = crate::allocator::heap_range().0 as u64;
+    if crate::paging::page_user_accessible(code) != Some(false) {
+        bits &= !CR4_SMEP;
+    }
+    if [stack, data, heap]
+        .iter()
+        .any(|&addr| crate::paging::page_user_accessible(addr) != Some(false))
+    {
+        bits &= !CR4_SMAP;
+    }
+    CR4_BITS.store(bits, Ordering::Release);
+    SMAP_ON.store(bits & CR4_SMAP != 0, Ordering::Release);
+    enable_on_this_cpu();
+    if bits != 0 {
+        crate::smp::run_on_firmware_aps(prepare_ap);
+    }
+    let state = |bit: u64| if bits & bit != 0 { "si" } else { "no" };
+    println(
+        alloc::format!(
+            "User access: SMEP {}, SMAP {}, UMIP {}",
+            state(CR4_SMEP),
+            state(CR4_SMAP),
+            state(CR4_UMIP)
+        )
+        .as_str(),
+    );
+}
+
+/// RFLAGS.AC set for as long as it lives. Windows do not nest.
+struct Window;
+
+impl Window {
+    fn open() -> Self {
+        if SMAP_ON.load(Ordering::Relaxed) {
+            unsafe { asm!("stac", options(nomem, nostack)) };
+        }
+        Window
+    }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        if SMAP_ON.load(Ordering::Relaxed) {
+            unsafe { asm!("clac", options(nomem, nostack)) };
+        }
+    }
+}
+
+/// Whether `[addr, addr + len)` may be a user range: inside the lower half
+/// and, during a guest call, inside what the guest has mapped.
+pub fn check(addr: u64, len: usize) -> Result<(), Fault> {
+    if len == 0 {
+        return Ok(());
+    }
+    let end = addr.checked_add(len as u64).ok_or(Fault)?;
+    if addr == 0 || end > USER_TOP {
+        return Err(Fault);
+    }
+    match GUEST.lock().as_ref() {
+        Some(regions) if !regions.covers(addr, end) => Err(Fault),
+        _ => Ok(()),
+    }
+}
+
+/// Copy between a checked user range and kernel memory.
+fn copy_raw(dst: *mut u8, src: *const u8, len: usize) -> Result<(), Fault> {
+    let _window = Window::open();
+    match unsafe { uaccess_copy(dst, src, len) } {
+        0 => Ok(()),
+        _ => Err(Fault),
+    }
+}
+
+pub fn copy_from_user(dst: &mut [u8], src: u64) -> Result<(), Fault> {
+    check(src, dst.len())?;
+    copy_raw(dst.as_mut_ptr(), src as *const u8, dst.len())
+}
+
+pub fn copy_to_user(dst: u64, src: &[u8]) -> Result<(), Fault> {
+    check(dst, src.len())?;
+    copy_raw(dst as *mut u8, src.as_ptr(), src.len())
+}
+
+pub fn clear_user(dst: u64, len: usize) -> Result<(), Fault> {
+    check(dst, len)?;
+    let _window = Window::open();
+    match unsafe { uaccess_clear(dst as *mut u8, len) } {
+        0 => Ok(()),
+        _ => Err(Fault),
+    }
+}
+
+/// Read a `T` from user memory; no alignment needed.
+pub fn read_user<T: Copy>(src: u64) -> Result<T, Fault> {
+    let len = core::mem::size_of::<T>();
+    let mut value = core::mem::MaybeUninit::<T>::uninit();
+    check(src, len)?;
+    copy_raw(value.as_mut_ptr() as *mut u8, src as *const u8, len)?;
+    // SAFETY: every byte was copied in; callers read plain-data types.
+    Ok(unsafe { value.assume_init() })
+}
+
+/// Write a `T` to user memory; no alignment needed.
+pub fn write_user<T: Copy>(dst: u64, value: T) -> Result<(), Fault> {
+    let len = core::mem::size_of::<T>();
+    check(dst, len)?;
+    copy_raw(dst as *mut u8, &value as *const T as *const u8, len)
+}
+
+/// Copy a NUL-terminated string into `out`, without the NUL. Returns its
+/// length, or `out.len()` when no NUL came first.
+pub fn copy_cstr_from_user(src: u64, out: &mut [u8]) -> Result<usize, Fault> {
+    if src == 0 {
+        return Err(Fault);
+    }
+    for index in 0..out.len() {
+        let addr = src.checked_add(index as u64).ok_or(Fault)?;
+        let mut byte = 0u8;
+        copy_from_user(core::slice::from_mut(&mut byte), addr)?;
+        if byte == 0 {
+            return Ok(index);
+        }
+        out[index] = byte;
+    }
+    Ok(out.len())
+}
+
+crate::selftest::kernel_tests! {
+    "uaccess";
+
+    fn range_checks() {
+        crate::selftest::ensure_eq(check(0, 0), Ok(()), "rango vacio")?;
+        crate::selftest::ensure_eq(check(0, 1), Err(Fault), "puntero nulo")?;
+        crate::selftest::ensure_eq(check(u64::MAX - 1, 4), Err(Fault), "desborde")?;
+        crate::selftest::ensure_eq(check(USER_TOP - 8, 16), Err(Fault), "mitad alta")?;
+        crate::selftest::ensure_eq(check(0x1000, 16), Ok(()), "rango valido")
+    }
+
+    fn guest_regions_cover() {
+        let mut regions = GuestRegions::new();
+        regions.push(0x1000, 0x1000);
+        regions.push(0x2000, 0x1000);
+        regions.push(0x8000, 0x100);
+        crate::selftest::ensure(regions.covers(0x1800, 0x2800), "rangos contiguos")?;
+        crate::selftest::ensure(regions.covers(0x8000, 0x8100), "rango exacto")?;
+        crate::selftest::ensure(!regions.covers(0x2800, 0x3001), "sale del mapeo")?;
+        crate::selftest::ensure(!regions.covers(0x7FFF, 0x8001), "empieza fuera")
+    }
+
+    fn copies_round_trip() {
+        let mut buffer = [0u8; 16];
+        let addr = buffer.as_mut_ptr() as u64;
+        copy_to_user(addr, b"hola\0mundo").map_err(|_| "copy_to_user")?;
+        let mut out = [0u8; 8];
+        crate::selftest::ensure_eq(copy_cstr_from_user(addr, &mut out), Ok(4), "longitud de cadena")?;
+        crate::selftest::ensure_eq(&out[..4], b"hola".as_slice(), "cadena")?;
+        write_user(addr + 1, 0x1122_3344u32).map_err(|_| "write_user")?;
+        crate::selftest::ensure_eq(read_user::<u32>(addr + 1), Ok(0x1122_3344), "valor sin alinear")?;
+        clear_user(addr, 4).map_err(|_| "clear_user")?;
+        let mut back = [0xFFu8; 6];
+        copy_from_user(&mut back, addr).map_err(|_| "copy_from_user")?;
+        crate::selftest::ensure_eq(back, [0, 0, 0, 0, 0x11, b'm'], "copia de vuelta")
+    }
+
+    fn window_leaves_ac_clear() {
+        let mut value = 0u64;
+        write_user(&mut value as *mut u64 as u64, 7).map_err(|_| "write_user")?;
+        let rflags: u64;
+        unsafe { asm!("pushfq", "pop {}", out(reg) rflags) };
+        crate::selftest::ensure(rflags & (1 << 18) == 0, "AC sigue activo")
+    }
+}
@@ -1,3 +1,4 @@
+use crate::fbcon;
 use crate::framebuffer::{self, rgb};
 
 const TOP_PANEL_H: usize = 64;
@@ -10,7 +11,6 @@ const INDICATOR_Y_OFFSET: usize = 12;
 pub const TERM_MAX_INPUT: usize = 72;
 const TERM_MAX_LINES: usize = 18;
 const TERM_MAX_COLS: usize = 72;
-const TERM_LINE_HEIGHT: usize = 9;
 
 #[derive(Clone, Copy)]
 struct ThemePalette {
@@ -56,10 +56,6 @@ const THEME_ALT: ThemePalette = ThemePalette {
 struct TerminalState {
     input: [u8; TERM_MAX_INPUT],
     input_len: usize,
-    lines: [[u8; TERM_MAX_COLS]; TERM_MAX_LINES],
-    line_lens: [usize; TERM_MAX_LINES],
-    head: usize,
-    count: usize,
 }
 
 impl TerminalState {
@@ -67,10 +63,6 @@ impl TerminalState {
         Self {
             input: [0; TERM_MAX_INPUT],
             input_len: 0,
-            lines: [[0; TERM_MAX_COLS]; TERM_MAX_LINES],
-            line_lens: [0; TERM_MAX_LINES],
-            head: 0,
-            count: 0,
         }
     }
 }
@@ -87,20 +79,7 @@ fn palette(alt_theme: bool) -> ThemePalette {
 }
 
 fn push_line_bytes(bytes: &[u8]) {
-    unsafe {
-        let idx = TERMINAL.head;
-        let n = bytes.len().min(TERM_MAX_COLS);
-        let mut i = 0usize;
-        while i < n {
-            TERMINAL.lines[idx][i] = bytes[i];
-            i += 1;
-        }
-        TERMINAL.line_lens[idx] = n;
-        TERMINAL.head = (TERMINAL.head + 1) % TERM_MAX_LINES;
-        if TERMINAL.count < TERM_MAX_LINES {
-            TERMINAL.count += 1;
-        }
-    }
+    fbcon::write_line_bytes(bytes);
 }
 
 fn push_line(text: &str) {
@@ -108,18 +87,14 @@ fn push_line(text: &str) {
 }
 
 fn clear_lines_only() {
-    unsafe {
-        TERMINAL.lines = [[0; TERM_MAX_COLS]; TERM_MAX_LINES];
-        TERMINAL.line_lens = [0; TERM_MAX_LINES];
-        TERMINAL.head = 0;
-        TERMINAL.count = 0;
-    }
+    fbcon::clear();
 }
 
 pub fn terminal_reset(irq_mode: bool) {
     unsafe {
         TERMINAL = TerminalState::new();
     }
+    clear_lines_only();
     push_line("REDUX TERMINAL READY");
     push_line("USER SPACE SHELL: ONLINE");
     if irq_mode {
@@ -133,6 +108,14 @@ pub fn terminal_clear_lines() {
     clear_lines_only();
 }
 
+pub fn terminal_page_up() {
+    fbcon::page_up();
+}
+
+pub fn terminal_page_down() {
+    fbcon::page_down();
+}
+
 pub fn terminal_system_message(msg: &str) {
     push_line(msg);
 }
@@ -145,6 +128,7 @@ pub fn terminal_input_char(ch: char) {
     if !ch.is_ascii() || ch == '\n' || ch == '\r' {
         return;
     }
+    fbcon::scroll_to_bottom();
     unsafe {
         if TERMINAL.input_len < TERM_MAX_INPUT {
             TERMINAL.input[TERMINAL.input_len] = ch as u8;
@@ -219,6 +203,7 @@ fn push_prompt_line() {
 }
 
 pub fn terminal_commit_input_line() {
+    fbcon::scroll_to_bottom();
     let (_, end) = trimmed_input_bounds();
     if end > 0 {
         push_prompt_line();
@@ -268,30 +253,16 @@ fn draw_terminal_panel(w: usize, h: usize, p: ThemePalette, ticks: u64) {
 
     let content_x = term_x + 8;
     let content_y = term_y + 22;
-    let max_visible = term_h.saturating_sub(40) / TERM_LINE_HEIGHT;
+    fbcon::render(
+        content_x,
+        content_y,
+        term_w.saturating_sub(16),
+        term_h.saturating_sub(40),
+        p.fg_text,
+        0x0C121D,
+    );
 
     unsafe {
-        let count = TERMINAL.count;
-        let visible = count.min(max_visible);
-        let skip = count.saturating_sub(visible);
-        let oldest = if count == TERM_MAX_LINES { TERMINAL.head } else { 0 };
-
-        let mut line_no = 0usize;
-        while line_no < visible {
-            let seq = skip + line_no;
-            let idx = (oldest + seq) % TERM_MAX_LINES;
-            let len = TERMINAL.line_lens[idx];
-            if len > 0 {
-                framebuffer::draw_text_5x7_bytes(
-                    content_x,
-                    content_y + line_no * TERM_LINE_HEIGHT,
-                    &TERMINAL.lines[idx][..len],
-                    p.fg_text,
-                );
-            }
-            line_no += 1;
-        }
-
         let input_y = term_y + term_h.saturating_sub(16);
         framebuffer::draw_text_5x7(content_x, input_y, "> ", p.fg_input);
         framebuffer::draw_text_5x7_bytes(
@@ -316,7 +287,8 @@ fn draw_right_panel(w: usize, _h: usize, p: ThemePalette) {
     framebuffer::draw_text_5x7(x, y + 16, "KEYBOARD: TYPE + ENTER", p.fg_text);
     framebuffer::draw_text_5x7(x, y + 28, "SHELL: HELP STATUS PS", p.fg_text);
     framebuffer::draw_text_5x7(x, y + 40, "F1 PAUSE  F2 THEME", p.fg_text);
-    framebuffer::draw_text_5x7(x, y + 52, "ESC REBOOT", p.fg_text);
+    framebuffer::draw_text_5x7(x, y + 52, "PGUP PGDN SCROLL", p.fg_text);
+    framebuffer::draw_text_5x7(x, y + 64, "ESC REBOOT", p.fg_text);
 }
 
 fn draw_metrics(
@@ -380,25 +352,11 @@ pub fn draw_desktop(
     draw_metrics(w, h, ticks, dispatches, irq_count, mem_mib, p);
     draw_activity_indicator(w, h, ticks, running);
 }
-pub fn for_each_line<F>(mut f: F)
+pub fn for_each_line<F>(f: F)
 where
     F: FnMut(&[u8]),
 {
-    unsafe {
-        let count = TERMINAL.count;
-        let visible = count.min(TERM_MAX_LINES);
-        let skip = count.saturating_sub(visible);
-        let oldest = if count == TERM_MAX_LINES { TERMINAL.head } else { 0 };
-
-        let mut line_no = 0usize;
-        while line_no < visible {
-            let seq = skip + line_no;
-            let idx = (oldest + seq) % TERM_MAX_LINES;
-            let len = TERMINAL.line_lens[idx];
-            f(&TERMINAL.lines[idx][..len]);
-            line_no += 1;
-        }
-    }
+    fbcon::for_each_line(TERM_MAX_LINES, f);
 }
 
 pub fn with_input<F>(f: F)
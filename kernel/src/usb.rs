@@ -0,0 +1,461 @@
+//! USB hotplug: devices plugged in or pulled out after boot.
+//!
+//! The firmware's USB bus driver enumerates new devices on its own; what was
+//! missing is everything after that. `poll` (run by the desktop) watches the
+//! xHCI root ports and, when one changes (or every `IDLE_RESCAN_MS` on
+//! machines without an xHCI controller we can read), compares the EFI_USB_IO
+//! handles with the ones it already knows:
+//!
+//! - a new handle is connected to the firmware's drivers (mass storage, boot
+//!   keyboard and mouse), gets a `usbN` node in the device model and a
+//!   notification; HID interfaces make `gamepad` and `touch` look again;
+//! - a handle that went away loses its node, and the drivers holding it are
+//!   rescanned so nothing keeps polling a dead protocol.
+//!
+//! Removable volumes are diffed the same way after a storage device comes or
+//! goes. New ones are reported to the desktop, which offers to mount them;
+//! losing the mounted one makes the desktop unmount it.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::device::DeviceId;
+use crate::spinlock::SpinLock;
+use crate::usbhid::{RawUsbIoProtocol, UsbDeviceDescriptor, UsbInterfaceDescriptor};
+
+/// Without port change information, look this often.
+const IDLE_RESCAN_MS: u64 = 2000;
+/// After a port change the firmware needs a moment to enumerate the device
+/// and bind its drivers; look quickly for this long.
+const SETTLE_WINDOW_MS: u64 = 4000;
+const SETTLE_RESCAN_MS: u64 = 250;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    Storage,
+    Keyboard,
+    Mouse,
+    Gamepad,
+    Hid,
+    Hub,
+    Audio,
+    Video,
+    Printer,
+    Other,
+}
+
+impl Kind {
+    /// From the device class and the class triple of the interface the
+    /// handle stands for.
+    pub fn classify(device_class: u8, class: u8, sub_class: u8, protocol: u8) -> Self {
+        match (class, sub_class, protocol) {
+            (0x08, _, _) => Kind::Storage,
+            (0x03, 0x01, 0x01) => Kind::Keyboard,
+            (0x03, 0x01, 0x02) => Kind::Mouse,
+            (0x03, _, _) => Kind::Hid,
+            (0xFF, 0x5D, 0x01) => Kind::Gamepad,
+            (0x09, _, _) => Kind::Hub,
+            (0x01, _, _) => Kind::Audio,
+            (0x0E, _, _) => Kind::Video,
+            (0x07, _, _) => Kind::Printer,
+            _ if device_class == 0x09 => Kind::Hub,
+            _ => Kind::Other,
+        }
+    }
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Kind::Storage => "Almacenamiento",
+            Kind::Keyboard => "Teclado",
+            Kind::Mouse => "Raton",
+            Kind::Gamepad => "Mando",
+            Kind::Hid => "Dispositivo HID",
+            Kind::Hub => "Hub",
+            Kind::Audio => "Audio",
+            Kind::Video => "Camara",
+            Kind::Printer => "Impresora",
+            Kind::Other => "Dispositivo",
+        }
+    }
+
+    /// Driver shown in the device model.
+    pub const fn driver(self) -> &'static str {
+        match self {
+            Kind::Storage => "usb-storage",
+            Kind::Keyboard => "usbkbd",
+            Kind::Mouse => "usbmouse",
+            Kind::Gamepad => "gamepad",
+            Kind::Hid => "usbhid",
+            Kind::Hub => "hub",
+            Kind::Audio => "snd-usb",
+            Kind::Video => "uvc",
+            Kind::Printer => "usblp",
+            Kind::Other => "usb",
+        }
+    }
+
+    const fn is_hid(self) -> bool {
+        matches!(self, Kind::Keyboard | Kind::Mouse | Kind::Gamepad | Kind::Hid)
+    }
+}
+
+#[derive(Clone)]
+struct Attached {
+    handle: usize,
+    vendor: u16,
+    product: u16,
+    kind: Kind,
+    node: DeviceId,
+}
+
+struct State {
+    attached: Vec<Attached>,
+    /// Removable block device handles at the last look.
+    volumes: Vec<usize>,
+    started: bool,
+    next_scan_ms: u64,
+    settle_until_ms: u64,
+}
+
+static STATE: SpinLock<State> = SpinLock::new(State {
+    attached: Vec::new(),
+    volumes: Vec::new(),
+    started: false,
+    next_scan_ms: 0,
+    settle_until_ms: 0,
+});
+
+/// A removable volume that appeared, for the desktop's mount offer.
+#[derive(Clone, Debug)]
+pub struct NewVolume {
+    pub handle: usize,
+    pub label: String,
+}
+
+#[derive(Default)]
+pub struct Changes {
+    pub new_volumes: Vec<NewVolume>,
+    /// Handles of removable volumes that went away.
+    pub removed_volumes: Vec<usize>,
+    /// Devices came or went: disk icons and device lists are stale.
+    pub devices_changed: bool,
+}
+
+/// Elements of `present` missing from `known`, and of `known` missing from
+/// `present`.
+pub fn diff<T: PartialEq + Copy>(known: &[T], present: &[T]) -> (Vec<T>, Vec<T>) {
+    let added = present.iter().filter(|h| !known.contains(h)).copied().collect();
+    let removed = known.iter().filter(|h| !present.contains(h)).copied().collect();
+    (added, removed)
+}
+
+/// Whether a scan is due at `now_ms`; a port change opens a settle window of
+/// quick rescans.
+fn scan_due(state: &mut State, now_ms: u64, port_changes: usize) -> bool {
+    if port_changes > 0 {
+        state.settle_until_ms = now_ms + SETTLE_WINDOW_MS;
+        state.next_scan_ms = now_ms;
+    }
+    if now_ms < state.next_scan_ms {
+        return false;
+    }
+    let idle_interval = if crate::xhci::controller_count() > 0 {
+        // The ports tell us when to look; this is only a safety net.
+        IDLE_RESCAN_MS * 5
+    } else {
+        IDLE_RESCAN_MS
+    };
+    state.next_scan_ms = now_ms
+        + if now_ms < state.settle_until_ms {
+            SETTLE_RESCAN_MS
+        } else {
+            idle_interval
+        };
+    true
+}
+
+fn handle_key(handle: uefi::Handle) -> usize {
+    handle.as_ptr() as usize
+}
+
+fn usb_handles() -> Vec<uefi::Handle> {
+    uefi::boot::find_handles::<RawUsbIoProtocol>().unwrap_or_default()
+}
+
+fn describe(handle: uefi::Handle) -> Option<(UsbDeviceDescriptor, UsbInterfaceDescriptor)> {
+    use uefi::boot::{OpenProtocolAttributes, OpenProtocolParams};
+
+    let params = OpenProtocolParams {
+        handle,
+        agent: uefi::boot::image_handle(),
+        controller: None,
+    };
+    let scoped =
+        unsafe { uefi::boot::open_protocol::<RawUsbIoProtocol>(params, OpenProtocolAttributes::GetProtocol) }.ok()?;
+    let raw = &*scoped as *const RawUsbIoProtocol as *mut RawUsbIoProtocol;
+    let mut device = UsbDeviceDescriptor::default();
+    let mut iface = UsbInterfaceDescriptor::default();
+    unsafe {
+        if ((*raw).get_interface_descriptor)(raw, &mut iface).is_error() {
+            return None;
+        }
+        let _ = ((*raw).get_device_descriptor)(raw, &mut device);
+    }
+    Some((device, iface))
+}
+
+fn attach(handle: uefi::Handle, announce: bool) -> Option<Attached> {
+    let (device, iface) = describe(handle)?;
+    let kind = Kind::classify(
+        device.device_class,
+        iface.interface_class,
+        iface.interface_sub_class,
+        iface.interface_protocol,
+    );
+    let (vendor, product) = (device.id_vendor, device.id_product);
+    if announce {
+        // Let the firmware bind its class drivers (mass storage, boot HID).
+        let _ = uefi::boot::connect_controller(handle, None, None, true);
+    }
+    let root = crate::device::add(None, "usb", None, None);
+    let node = crate::device::add_class_device(Some(root), "usb", "usb", kind.driver());
+    crate::device::set_attr(node, "vendor", alloc::format!("0x{:04x}", vendor).as_str());
+    crate::device::set_attr(node, "product", alloc::format!("0x{:04x}", product).as_str());
+    crate::device::set_attr(
+        node,
+        "interface",
+        alloc::format!(
+            "{:02x}/{:02x}/{:02x}",
+            iface.interface_class,
+            iface.interface_sub_class,
+            iface.interface_protocol
+        )
+        .as_str(),
+    );
+    crate::device::set_attr(node, "kind", kind.label());
+    Some(Attached {
+        handle: handle_key(handle),
+        vendor,
+        product,
+        kind,
+        node,
+    })
+}
+
+fn removable_volumes() -> Vec<crate::fat32::DetectedBlockDevice> {
+    crate::fat32::Fat32::detect_uefi_block_devices()
+        .into_iter()
+        .filter(|dev| dev.removable)
+        .collect()
+}
+
+fn volume_label(dev: &crate::fat32::DetectedBlockDevice) -> String {
+    alloc::format!("USB {} [{}] ({} MiB)", dev.index, dev.fs_kind.as_str(), dev.total_mib)
+}
+
+/// Look for arrivals and removals. Cheap when nothing is due.
+pub fn poll() -> Changes {
+    let mut changes = Changes::default();
+    let now = crate::timer::now_ms();
+    let ports = crate::xhci::port_changes();
+    let mut state = STATE.lock();
+    if !state.started {
+        // Devices present at boot were set up by the firmware and the boot
+        // path; register them without notifications.
+        state.started = true;
+        let attached: Vec<Attached> = usb_handles().into_iter().filter_map(|h| attach(h, false)).collect();
+        state.attached = attached;
+        state.volumes = removable_volumes().iter().map(|dev| handle_key(dev.handle)).collect();
+        state.next_scan_ms = now + IDLE_RESCAN_MS;
+        return changes;
+    }
+    if !scan_due(&mut state, now, ports) {
+        return changes;
+    }
+
+    let handles = usb_handles();
+    let known: Vec<usize> = state.attached.iter().map(|a| a.handle).collect();
+    let present: Vec<usize> = handles.iter().map(|h| handle_key(*h)).collect();
+    let (added, removed) = diff(&known, &present);
+    let mut rescan_hid = false;
+    let mut storage_moved = false;
+
+    for key in removed.iter() {
+        let Some(pos) = state.attached.iter().position(|a| a.handle == *key) else {
+            continue;
+        };
+        let gone = state.attached.remove(pos);
+        crate::device::remove(gone.node);
+        rescan_hid |= gone.kind.is_hid();
+        storage_moved |= gone.kind == Kind::Storage;
+        crate::gui::notifications::post(
+            "usb",
+            "USB desconectado",
+            alloc::format!("{} {:04x}:{:04x}", gone.kind.label(), gone.vendor, gone.product).as_str(),
+            crate::gui::notifications::Urgency::Info,
+        );
+    }
+    for handle in handles.iter().filter(|h| added.contains(&handle_key(**h))) {
+        let Some(new) = attach(*handle, true) else {
+            continue;
+        };
+        rescan_hid |= new.kind.is_hid();
+        storage_moved |= new.kind == Kind::Storage;
+        if new.kind != Kind::Storage {
+            // Storage is announced once its volume shows up.
+            crate::gui::notifications::post(
+                "usb",
+                "USB conectado",
+                alloc::format!("{} {:04x}:{:04x}", new.kind.label(), new.vendor, new.product).as_str(),
+                crate::gui::notifications::Urgency::Info,
+            );
+        }
+        state.attached.push(new);
+    }
+
+    // A stick's volume can appear a little after its USB handle; keep
+    // looking while the port settles.
+    if storage_moved || (now < state.settle_until_ms && state.attached.iter().any(|a| a.kind == Kind::Storage)) {
+        let volumes = removable_volumes();
+        let present: Vec<usize> = volumes.iter().map(|dev| handle_key(dev.handle)).collect();
+        let (added, removed) = diff(&state.volumes, &present);
+        for dev in volumes.iter().filter(|dev| added.contains(&handle_key(dev.handle))) {
+            let label = volume_label(dev);
+            crate::gui::notifications::post(
+                "usb",
+                "Unidad USB conectada",
+                alloc::format!("{} - montar?", label).as_str(),
+                crate::gui::notifications::Urgency::Info,
+            );
+            changes.new_volumes.push(NewVolume {
+                handle: handle_key(dev.handle),
+                label,
+            });
+        }
+        changes.removed_volumes = removed;
+        state.volumes = present;
+    }
+    changes.devices_changed = !added.is_empty()
+        || !removed.is_empty()
+        || !changes.new_volumes.is_empty()
+        || !changes.removed_volumes.is_empty();
+    drop(state);
+
+    if rescan_hid {
+        crate::gamepad::reset_uefi();
+        crate::touch::reset_uefi();
+    }
+    if changes.devices_changed {
+        crate::klog::log(
+            crate::klog::Level::Info,
+            alloc::format!(
+                "usb: +{} -{} dispositivos, +{} -{} volumenes",
+                added.len(),
+                removed.len(),
+                changes.new_volumes.len(),
+                changes.removed_volumes.len()
+            )
+            .as_str(),
+        );
+    }
+    changes
+}
+
+/// Current index of a removable volume in `Fat32::detect_uefi_block_devices`.
+pub fn volume_index(handle: usize) -> Option<usize> {
+    removable_volumes()
+        .iter()
+        .find(|dev| handle_key(dev.handle) == handle)
+        .map(|dev| dev.index)
+}
+
+/// Look again on the next `poll`.
+pub fn request_rescan() {
+    let mut state = STATE.lock();
+    state.next_scan_ms = 0;
+    state.settle_until_ms = crate::timer::now_ms() + SETTLE_WINDOW_MS;
+}
+
+/// Shared implementation of `usb [list|rescan]`.
+pub fn command_lines(args: &str) -> Vec<String> {
+    match args.trim() {
+        "" | "list" => {
+            let state = STATE.lock();
+            let mut out = alloc::vec![alloc::format!(
+                "Dispositivos USB: {}  (controladores xHCI: {})",
+                state.attached.len(),
+                crate::xhci::controller_count()
+            )];
+            if !state.started {
+                out.push(String::from("  (el escritorio aun no ha revisado el bus)"));
+            }
+            for dev in state.attached.iter() {
+                let name = crate::device::info(dev.node).map(|i| i.name).unwrap_or_default();
+                out.push(alloc::format!(
+                    "  {:<6} {:04x}:{:04x}  {}  ({})",
+                    name,
+                    dev.vendor,
+                    dev.product,
+                    dev.kind.label(),
+                    dev.kind.driver()
+                ));
+            }
+            drop(state);
+            for (controller, port, status) in crate::xhci::connected_ports() {
+                out.push(alloc::format!(
+                    "  puerto {}-{}: {}{}",
+                    controller,
+                    port,
+                    crate::xhci::speed_name(status.speed),
+                    if status.enabled { "" } else { ", deshabilitado" }
+                ));
+            }
+            out
+        }
+        "rescan" => {
+            request_rescan();
+            alloc::vec![String::from(
+                "USB: se revisara el bus en el proximo ciclo del escritorio."
+            )]
+        }
+        _ => alloc::vec![String::from("Uso: usb [list|rescan]")],
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "usb";
+
+    fn interfaces_classify_by_class_triple() {
+        crate::selftest::ensure_eq(Kind::classify(0, 0x08, 0x06, 0x50), Kind::Storage, "bulk-only")?;
+        crate::selftest::ensure_eq(Kind::classify(0, 0x03, 0x01, 0x01), Kind::Keyboard, "teclado")?;
+        crate::selftest::ensure_eq(Kind::classify(0, 0x03, 0x01, 0x02), Kind::Mouse, "raton")?;
+        crate::selftest::ensure_eq(Kind::classify(0, 0x03, 0x00, 0x00), Kind::Hid, "hid")?;
+        crate::selftest::ensure_eq(Kind::classify(0xFF, 0xFF, 0x5D, 0x01), Kind::Gamepad, "xinput")?;
+        crate::selftest::ensure_eq(Kind::classify(0x09, 0xFF, 0x00, 0x00), Kind::Hub, "hub por clase de dispositivo")?;
+        crate::selftest::ensure_eq(Kind::classify(0, 0xFF, 0x00, 0x00), Kind::Other, "vendor")
+    }
+
+    fn handle_sets_diff_both_ways() {
+        let (added, removed) = diff(&[1usize, 2, 3], &[2usize, 3, 4, 5]);
+        crate::selftest::ensure_eq(added, alloc::vec![4usize, 5], "nuevos")?;
+        crate::selftest::ensure_eq(removed, alloc::vec![1usize], "retirados")?;
+        let (added, removed) = diff::<usize>(&[], &[]);
+        crate::selftest::ensure(added.is_empty() && removed.is_empty(), "vacio")
+    }
+
+    fn port_change_opens_settle_window() {
+        let mut state = State {
+            attached: Vec::new(),
+            volumes: Vec::new(),
+            started: true,
+            next_scan_ms: 10_000,
+            settle_until_ms: 0,
+        };
+        crate::selftest::ensure(!scan_due(&mut state, 5_000, 0), "sin cambios aun no toca")?;
+        crate::selftest::ensure(scan_due(&mut state, 5_000, 1), "cambio de puerto")?;
+        crate::selftest::ensure_eq(state.next_scan_ms, 5_000 + SETTLE_RESCAN_MS, "revision rapida")?;
+        crate::selftest::ensure(scan_due(&mut state, 5_000 + SETTLE_RESCAN_MS, 0), "sigue revisando")?;
+        crate::selftest::ensure(scan_due(&mut state, 5_000 + SETTLE_WINDOW_MS, 0), "fin de ventana")?;
+        crate::selftest::ensure(state.next_scan_ms >= 5_000 + SETTLE_WINDOW_MS + IDLE_RESCAN_MS, "vuelve a reposo")
+    }
+}
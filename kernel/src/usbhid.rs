@@ -0,0 +1,233 @@
+//! USB I/O protocol access and HID helpers shared by the drivers that read
+//! USB HID devices through the firmware's bus driver (`gamepad`, `touch`).
+
+use alloc::vec::Vec;
+
+pub const HID_DESCRIPTOR_MAX_BYTES: usize = 1024;
+
+// ---------------------------------------------------------------------------
+// EFI_USB_IO_PROTOCOL — raw implementation
+// UEFI spec: Section 17.2 — USB I/O Protocol
+// GUID: 2B2F68D6-0CD2-44CF-8E8B-BBA20B1B5B75
+// ---------------------------------------------------------------------------
+
+#[repr(C)]
+pub struct UsbDeviceRequest {
+    pub request_type: u8,
+    pub request: u8,
+    pub value: u16,
+    pub index: u16,
+    pub length: u16,
+}
+
+#[repr(C, packed)]
+#[derive(Clone, Copy, Default)]
+pub struct UsbDeviceDescriptor {
+    pub length: u8,
+    pub descriptor_type: u8,
+    pub bcd_usb: u16,
+    pub device_class: u8,
+    pub device_sub_class: u8,
+    pub device_protocol: u8,
+    pub max_packet_size0: u8,
+    pub id_vendor: u16,
+    pub id_product: u16,
+    pub bcd_device: u16,
+    pub str_manufacturer: u8,
+    pub str_product: u8,
+    pub str_serial_number: u8,
+    pub num_configurations: u8,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct UsbInterfaceDescriptor {
+    pub length: u8,
+    pub descriptor_type: u8,
+    pub interface_number: u8,
+    pub alternate_setting: u8,
+    pub num_endpoints: u8,
+    pub interface_class: u8,
+    pub interface_sub_class: u8,
+    pub interface_protocol: u8,
+    pub interface: u8,
+}
+
+#[repr(C, packed)]
+#[derive(Clone, Copy, Default)]
+pub struct UsbEndpointDescriptor {
+    pub length: u8,
+    pub descriptor_type: u8,
+    pub endpoint_address: u8,
+    pub attributes: u8,
+    pub max_packet_size: u16,
+    pub interval: u8,
+}
+
+pub const USB_DATA_IN: u32 = 0;
+pub const USB_NO_DATA: u32 = 2;
+
+#[repr(C)]
+pub struct RawUsbIoProtocol {
+    pub control_transfer: unsafe extern "efiapi" fn(
+        this: *mut RawUsbIoProtocol,
+        request: *mut UsbDeviceRequest,
+        direction: u32,
+        timeout_ms: u32,
+        data: *mut core::ffi::c_void,
+        data_length: usize,
+        usb_status: *mut u32,
+    ) -> uefi::Status,
+    _bulk_transfer: *mut core::ffi::c_void,
+    _async_interrupt_transfer: *mut core::ffi::c_void,
+    pub sync_interrupt_transfer: unsafe extern "efiapi" fn(
+        this: *mut RawUsbIoProtocol,
+        endpoint: u8,
+        data: *mut core::ffi::c_void,
+        data_length: *mut usize,
+        timeout_ms: usize,
+        usb_status: *mut u32,
+    ) -> uefi::Status,
+    _isochronous_transfer: *mut core::ffi::c_void,
+    _async_isochronous_transfer: *mut core::ffi::c_void,
+    pub get_device_descriptor:
+        unsafe extern "efiapi" fn(this: *mut RawUsbIoProtocol, descriptor: *mut UsbDeviceDescriptor) -> uefi::Status,
+    _get_config_descriptor: *mut core::ffi::c_void,
+    pub get_interface_descriptor:
+        unsafe extern "efiapi" fn(this: *mut RawUsbIoProtocol, descriptor: *mut UsbInterfaceDescriptor) -> uefi::Status,
+    pub get_endpoint_descriptor: unsafe extern "efiapi" fn(
+        this: *mut RawUsbIoProtocol,
+        index: u8,
+        descriptor: *mut UsbEndpointDescriptor,
+    ) -> uefi::Status,
+    _get_string_descriptor: *mut core::ffi::c_void,
+    _get_supported_languages: *mut core::ffi::c_void,
+    _port_reset: *mut core::ffi::c_void,
+}
+
+unsafe impl uefi::Identify for RawUsbIoProtocol {
+    const GUID: uefi::Guid = uefi::Guid::from_bytes([
+        0xD6, 0x68, 0x2F, 0x2B, 0xD2, 0x0C, 0xCF, 0x44, 0x8E, 0x8B, 0xBB, 0xA2, 0x0B, 0x1B, 0x5B, 0x75,
+    ]);
+}
+
+impl uefi::proto::Protocol for RawUsbIoProtocol {}
+
+/// Interrupt IN endpoint of the interface: (address, max packet size).
+pub unsafe fn interrupt_in_endpoint(proto: *mut RawUsbIoProtocol, count: u8) -> Option<(u8, u16)> {
+    for index in 0..count {
+        let mut ep = UsbEndpointDescriptor::default();
+        if ((*proto).get_endpoint_descriptor)(proto, index, &mut ep).is_error() {
+            continue;
+        }
+        if ep.endpoint_address & 0x80 != 0 && ep.attributes & 0x03 == 0x03 {
+            return Some((ep.endpoint_address, ep.max_packet_size));
+        }
+    }
+    None
+}
+
+pub unsafe fn read_hid_report_descriptor(proto: *mut RawUsbIoProtocol, interface: u8) -> Option<Vec<u8>> {
+    let mut buf = alloc::vec![0u8; HID_DESCRIPTOR_MAX_BYTES];
+    let mut request = UsbDeviceRequest {
+        request_type: 0x81,
+        request: 0x06,
+        value: 0x2200,
+        index: interface as u16,
+        length: buf.len() as u16,
+    };
+    let mut usb_status = 0u32;
+    let status = ((*proto).control_transfer)(
+        proto,
+        &mut request,
+        USB_DATA_IN,
+        100,
+        buf.as_mut_ptr() as *mut core::ffi::c_void,
+        buf.len(),
+        &mut usb_status,
+    );
+    if status.is_error() {
+        return None;
+    }
+    // The transfer length is not reported back; trailing zeros parse as
+    // empty main items and are harmless.
+    Some(buf)
+}
+
+/// SET_IDLE 0: only report on change.
+pub unsafe fn hid_set_idle(proto: *mut RawUsbIoProtocol, interface: u8) {
+    let mut request = UsbDeviceRequest {
+        request_type: 0x21,
+        request: 0x0A,
+        value: 0,
+        index: interface as u16,
+        length: 0,
+    };
+    let mut usb_status = 0u32;
+    let _ = ((*proto).control_transfer)(
+        proto,
+        &mut request,
+        USB_NO_DATA,
+        100,
+        core::ptr::null_mut(),
+        0,
+        &mut usb_status,
+    );
+}
+
+// ---------------------------------------------------------------------------
+// HID report descriptors
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Default)]
+pub struct HidGlobals {
+    pub page: u16,
+    pub logical_min: i32,
+    pub logical_max_signed: i32,
+    pub logical_max_unsigned: i32,
+    pub report_size: u32,
+    pub report_count: u32,
+    pub report_id: u8,
+}
+
+impl HidGlobals {
+    /// Descriptors often write 255 as a one-byte 0xFF; read the maximum as
+    /// unsigned when the signed reading falls below a non-negative minimum.
+    pub fn logical_max(&self) -> i32 {
+        if self.logical_min >= 0 && self.logical_max_signed < self.logical_min {
+            self.logical_max_unsigned
+        } else {
+            self.logical_max_signed
+        }
+    }
+}
+
+/// A four-byte usage carries its own page.
+pub fn extended_usage(page: u16, data: u32, size: usize) -> (u16, u16) {
+    if size == 4 {
+        ((data >> 16) as u16, data as u16)
+    } else {
+        (page, data as u16)
+    }
+}
+
+pub fn read_bits(data: &[u8], offset: u32, size: u8) -> Option<u32> {
+    let mut value = 0u32;
+    for bit in 0..size as u32 {
+        let pos = offset + bit;
+        let byte = *data.get((pos / 8) as usize)?;
+        if byte & (1 << (pos % 8)) != 0 {
+            value |= 1 << bit;
+        }
+    }
+    Some(value)
+}
+
+/// Two's-complement value of a `bits`-wide field.
+pub fn sign_extend(raw: u32, bits: u8) -> i32 {
+    if bits == 0 || bits >= 32 {
+        return raw as i32;
+    }
+    let shift = 32 - bits as u32;
+    ((raw << shift) as i32) >> shift
+}
@@ -91,6 +91,122 @@ fn sys_priv_unsafe_test(tid: usize) -> u64 {
     syscall::invoke(tid, syscall::SYS_PRIV_UNSAFE_TEST, 0, 0, 0, 0)
 }
 
+#[inline]
+fn sys_http_open(tid: usize, method: &[u8], url: &[u8]) -> u64 {
+    syscall::invoke(
+        tid,
+        syscall::SYS_HTTP_OPEN,
+        method.as_ptr() as u64,
+        method.len() as u64,
+        url.as_ptr() as u64,
+        url.len() as u64,
+    )
+}
+
+#[inline]
+fn sys_http_send(tid: usize, handle: u64, body: &[u8]) -> u64 {
+    syscall::invoke(
+        tid,
+        syscall::SYS_HTTP_SEND,
+        handle,
+        body.as_ptr() as u64,
+        body.len() as u64,
+        0,
+    )
+}
+
+#[inline]
+fn sys_http_read(tid: usize, handle: u64, out: &mut [u8]) -> u64 {
+    syscall::invoke(
+        tid,
+        syscall::SYS_HTTP_READ,
+        handle,
+        out.as_mut_ptr() as u64,
+        out.len() as u64,
+        0,
+    )
+}
+
+#[inline]
+fn sys_http_close(tid: usize, handle: u64) {
+    let _ = syscall::invoke(tid, syscall::SYS_HTTP_CLOSE, handle, 0, 0, 0);
+}
+
+#[inline]
+fn sys_config_get(tid: usize, key: &[u8], out: &mut [u8]) -> u64 {
+    syscall::invoke(
+        tid,
+        syscall::SYS_CONFIG_GET,
+        key.as_ptr() as u64,
+        key.len() as u64,
+        out.as_mut_ptr() as u64,
+        out.len() as u64,
+    )
+}
+
+#[inline]
+fn sys_config_set(tid: usize, key: &[u8], value: &[u8]) -> u64 {
+    syscall::invoke(
+        tid,
+        syscall::SYS_CONFIG_SET,
+        key.as_ptr() as u64,
+        key.len() as u64,
+        value.as_ptr() as u64,
+        value.len() as u64,
+    )
+}
+
+#[inline]
+fn sys_fs_watch(tid: usize, path: &[u8]) -> u64 {
+    syscall::invoke(
+        tid,
+        syscall::SYS_FS_WATCH,
+        path.as_ptr() as u64,
+        path.len() as u64,
+        0,
+        0,
+    )
+}
+
+#[inline]
+fn sys_fs_read_events(tid: usize, wd: u64, out: &mut [u8]) -> u64 {
+    syscall::invoke(
+        tid,
+        syscall::SYS_FS_READ_EVENTS,
+        wd,
+        out.as_mut_ptr() as u64,
+        out.len() as u64,
+        0,
+    )
+}
+
+#[inline]
+fn sys_fs_unwatch(tid: usize, wd: u64) -> u64 {
+    syscall::invoke(tid, syscall::SYS_FS_UNWATCH, wd, 0, 0, 0)
+}
+
+#[inline]
+fn sys_audio_capture_open(tid: usize) -> u64 {
+    syscall::invoke(tid, syscall::SYS_AUDIO_CAPTURE_OPEN, 0, 0, 0, 0)
+}
+
+#[inline]
+fn sys_audio_capture_read(tid: usize, handle: u64, out: &mut [u8]) -> u64 {
+    syscall::invoke(
+        tid,
+        syscall::SYS_AUDIO_CAPTURE_READ,
+        handle,
+        out.as_mut_ptr() as u64,
+        out.len() as u64,
+        0,
+    )
+}
+
+#[inline]
+fn sys_audio_capture_close(tid: usize, handle: u64) -> u64 {
+    syscall::invoke(tid, syscall::SYS_AUDIO_CAPTURE_CLOSE, handle, 0, 0, 0)
+}
+
 fn to_upper_byte(b: u8) -> u8 {
     if b.is_ascii_lowercase() {
         b - 32
@@ -282,6 +398,195 @@ fn print_priv_status(tid: usize) {
     sys_write_line(tid, &line2[..n2]);
 }
 
+fn http_error_text(code: u64) -> &'static [u8] {
+    match code {
+        syscall::SYS_ERR_DENIED => b"HTTP: BLOCKED BY FIREWALL",
+        syscall::SYS_ERR_QUOTA => b"HTTP: NET QUOTA EXCEEDED",
+        syscall::SYS_ERR_NET => b"HTTP: NETWORK ERROR",
+        syscall::SYS_ERR_UNSUPPORTED => b"HTTP: UNSUPPORTED",
+        _ => b"HTTP: INVALID REQUEST",
+    }
+}
+
+fn http_get(tid: usize, url: &[u8]) {
+    let handle = sys_http_open(tid, b"GET", url);
+    if handle >= syscall::SYS_ERR_INVALID {
+        sys_write_line(tid, http_error_text(handle));
+        return;
+    }
+
+    let status = sys_http_send(tid, handle, &[]);
+    if status >= syscall::SYS_ERR_INVALID {
+        sys_write_line(tid, http_error_text(status));
+        sys_http_close(tid, handle);
+        return;
+    }
+
+    let mut line = [0u8; 32];
+    let mut n = 0usize;
+    n = append_bytes(&mut line, n, b"HTTP STATUS ");
+    n = append_u64(&mut line, n, status);
+    sys_write_line(tid, &line[..n]);
+
+    // First few lines of the body only; the terminal is not a pager.
+    let mut chunk = [0u8; 64];
+    let mut shown = 0usize;
+    while shown < 8 {
+        let got = sys_http_read(tid, handle, &mut chunk);
+        if got == 0 || got > chunk.len() as u64 {
+            break;
+        }
+        sys_write_line(tid, &chunk[..got as usize]);
+        shown += 1;
+    }
+    sys_http_close(tid, handle);
+}
+
+fn config_get(tid: usize, key: &[u8]) {
+    let mut value = [0u8; 96];
+    let len = sys_config_get(tid, key, &mut value);
+    if len == syscall::SYS_ERR_NOT_FOUND {
+        sys_write_line(tid, b"CONFIG: KEY NOT FOUND");
+        return;
+    }
+    if len >= syscall::SYS_ERR_INVALID {
+        sys_write_line(tid, b"CONFIG: INVALID KEY");
+        return;
+    }
+    let mut line = [0u8; 176];
+    let mut n = 0usize;
+    n = append_bytes(&mut line, n, key);
+    n = append_bytes(&mut line, n, b" = ");
+    n = append_bytes(&mut line, n, &value[..(len as usize).min(value.len())]);
+    sys_write_line(tid, &line[..n]);
+}
+
+fn config_set(tid: usize, args: &[u8]) {
+    let split = args.iter().position(|&b| b == b' ').unwrap_or(args.len());
+    let (key, rest) = args.split_at(split);
+    let (start, end) = trim_bounds(rest);
+    let status = sys_config_set(tid, key, &rest[start..end]);
+    match status {
+        0 => sys_write_line(tid, b"CONFIG: OK"),
+        syscall::SYS_ERR_PERMISSION => sys_write_line(tid, b"CONFIG: KEY IS READ-ONLY"),
+        _ => sys_write_line(tid, b"CONFIG: INVALID KEY OR VALUE"),
+    }
+}
+
+fn parse_u64(buf: &[u8]) -> Option<u64> {
+    if buf.is_empty() {
+        return None;
+    }
+    let mut value = 0u64;
+    for &b in buf.iter() {
+        if !b.is_ascii_digit() {
+            return None;
+        }
+        value = value.checked_mul(10)?.checked_add((b - b'0') as u64)?;
+    }
+    Some(value)
+}
+
+fn fs_watch(tid: usize, path: &[u8]) {
+    let wd = sys_fs_watch(tid, path);
+    if wd == syscall::SYS_ERR_NOT_FOUND {
+        sys_write_line(tid, b"WATCH: PATH NOT FOUND");
+        return;
+    }
+    if wd >= syscall::SYS_ERR_NOT_FOUND {
+        sys_write_line(tid, b"WATCH: FAILED");
+        return;
+    }
+    let mut line = [0u8; 32];
+    let mut n = append_bytes(&mut line, 0, b"WATCH: WD ");
+    n = append_u64(&mut line, n, wd);
+    sys_write_line(tid, &line[..n]);
+}
+
+fn fs_events(tid: usize, wd_text: &[u8]) {
+    let Some(wd) = parse_u64(wd_text) else {
+        sys_write_line(tid, b"EVENTS: BAD WD");
+        return;
+    };
+    let mut buf = [0u8; 512];
+    let len = sys_fs_read_events(tid, wd, &mut buf);
+    if len >= syscall::SYS_ERR_NOT_FOUND {
+        sys_write_line(tid, b"EVENTS: BAD WD");
+        return;
+    }
+    if len == 0 {
+        sys_write_line(tid, b"EVENTS: NONE");
+        return;
+    }
+    let records = &buf[..(len as usize).min(buf.len())];
+    let mut pos = 0usize;
+    while pos + 8 <= records.len() {
+        let kind: &[u8] = match records[pos] {
+            1 => b"CREATED ",
+            2 => b"WRITTEN ",
+            3 => b"REMOVED ",
+            4 => b"MOVED FROM ",
+            5 => b"MOVED TO ",
+            _ => b"OVERFLOW, RESCAN",
+        };
+        let name_len = u16::from_le_bytes([records[pos + 2], records[pos + 3]]) as usize;
+        let name_end = (pos + 8 + name_len).min(records.len());
+        let mut line = [0u8; 96];
+        let mut n = append_bytes(&mut line, 0, kind);
+        n = append_bytes(&mut line, n, &records[pos + 8..name_end]);
+        sys_write_line(tid, &line[..n]);
+        pos = name_end;
+    }
+}
+
+fn mic_open(tid: usize) {
+    let handle = sys_audio_capture_open(tid);
+    if handle == syscall::SYS_ERR_UNSUPPORTED {
+        sys_write_line(tid, b"MIC: NO INPUT DEVICE");
+        return;
+    }
+    if handle >= syscall::SYS_ERR_NOT_FOUND {
+        sys_write_line(tid, b"MIC: FAILED");
+        return;
+    }
+    let mut line = [0u8; 32];
+    let mut n = append_bytes(&mut line, 0, b"MIC: HANDLE ");
+    n = append_u64(&mut line, n, handle);
+    sys_write_line(tid, &line[..n]);
+}
+
+// Drains what was captured since the last read and reports its peak.
+fn mic_read(tid: usize, handle_text: &[u8]) {
+    let Some(handle) = parse_u64(handle_text) else {
+        sys_write_line(tid, b"MIC: BAD HANDLE");
+        return;
+    };
+    let mut buf = [0u8; 4096];
+    let mut samples = 0u64;
+    let mut peak = 0u64;
+    loop {
+        let len = sys_audio_capture_read(tid, handle, &mut buf);
+        if len >= syscall::SYS_ERR_NOT_FOUND {
+            sys_write_line(tid, b"MIC: BAD HANDLE");
+            return;
+        }
+        if len == 0 {
+            break;
+        }
+        for pair in buf[..(len as usize).min(buf.len())].chunks_exact(2) {
+            let sample = i16::from_le_bytes([pair[0], pair[1]]);
+            peak = peak.max(sample.unsigned_abs() as u64);
+            samples += 1;
+        }
+    }
+    let mut line = [0u8; 64];
+    let mut n = append_bytes(&mut line, 0, b"MIC: ");
+    n = append_u64(&mut line, n, samples);
+    n = append_bytes(&mut line, n, b" SAMPLES PEAK ");
+    n = append_u64(&mut line, n, peak);
+    sys_write_line(tid, &line[..n]);
+}
+
 fn handle_shell_command(tid: usize, cmd: &[u8]) {
     let (start, end) = trim_bounds(cmd);
     if end <= start {
@@ -293,7 +598,11 @@ fn handle_shell_command(tid: usize, cmd: &[u8]) {
     if eq_upper(text, b"HELP") {
         sys_write_line(tid, b"CMDS: HELP CLEAR ABOUT STATUS ECHO <TXT>");
         sys_write_line(tid, b"CMDS: PS SYSCALLS PRIV PRIV NEXT");
-        sys_write_line(tid, b"CMDS: PRIV UNSAFE");
+        sys_write_line(tid, b"CMDS: PRIV UNSAFE HTTP <URL>");
+        sys_write_line(tid, b"CMDS: CONFIG GET <KEY> CONFIG SET <KEY> [VAL]");
+        sys_write_line(tid, b"CMDS: WATCH <PATH> EVENTS <WD> UNWATCH <WD>");
+        sys_write_line(tid, b"CMDS: MIC OPEN MIC READ <H> MIC CLOSE <H>");
+        sys_write_line(tid, b"CMDS: DMESG [N]  PGUP/PGDN SCROLL");
         return;
     }
 
@@ -341,6 +650,65 @@ fn handle_shell_command(tid: usize, cmd: &[u8]) {
         return;
     }
 
+    if starts_with_upper(text, b"HTTP ") {
+        let (start, end) = trim_bounds(&text[5..]);
+        http_get(tid, &text[5 + start..5 + end]);
+        return;
+    }
+
+    if starts_with_upper(text, b"CONFIG GET ") {
+        let (start, end) = trim_bounds(&text[11..]);
+        config_get(tid, &text[11 + start..11 + end]);
+        return;
+    }
+
+    if starts_with_upper(text, b"CONFIG SET ") {
+        let (start, end) = trim_bounds(&text[11..]);
+        config_set(tid, &text[11 + start..11 + end]);
+        return;
+    }
+
+    if starts_with_upper(text, b"WATCH ") {
+        let (start, end) = trim_bounds(&text[6..]);
+        fs_watch(tid, &text[6 + start..6 + end]);
+        return;
+    }
+
+    if starts_with_upper(text, b"EVENTS ") {
+        let (start, end) = trim_bounds(&text[7..]);
+        fs_events(tid, &text[7 + start..7 + end]);
+        return;
+    }
+
+    if starts_with_upper(text, b"UNWATCH ") {
+        let (start, end) = trim_bounds(&text[8..]);
+        match parse_u64(&text[8 + start..8 + end]) {
+            Some(wd) if sys_fs_unwatch(tid, wd) == 0 => sys_write_line(tid, b"UNWATCH: OK"),
+            _ => sys_write_line(tid, b"UNWATCH: BAD WD"),
+        }
+        return;
+    }
+
+    if eq_upper(text, b"MIC OPEN") {
+        mic_open(tid);
+        return;
+    }
+
+    if starts_with_upper(text, b"MIC READ ") {
+        let (start, end) = trim_bounds(&text[9..]);
+        mic_read(tid, &text[9 + start..9 + end]);
+        return;
+    }
+
+    if starts_with_upper(text, b"MIC CLOSE ") {
+        let (start, end) = trim_bounds(&text[10..]);
+        match parse_u64(&text[10 + start..10 + end]) {
+            Some(handle) if sys_audio_capture_close(tid, handle) == 0 => sys_write_line(tid, b"MIC: CLOSED"),
+            _ => sys_write_line(tid, b"MIC: BAD HANDLE"),
+        }
+        return;
+    }
+
     if starts_with_upper(text, b"ECHO ") {
         if text.len() > 5 {
             sys_write_line(tid, &text[5..]);
@@ -1,13 +1,43 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{fence, AtomicBool, AtomicU64, Ordering};
+use core::task::{Context, Poll};
+
 use crate::pci::PciDevice;
-use crate::virtio::{VirtioDevice, VIRTIO_STATUS_ACKNOWLEDGE, VIRTIO_STATUS_DRIVER, VIRTIO_STATUS_DRIVER_OK, VIRTIO_STATUS_FAILED};
+use crate::virtio::modern::{ModernTransport, VIRTIO_F_VERSION_1, VIRTIO_RING_F_INDIRECT_DESC};
+use crate::virtio::{VirtioDevice, VIRTIO_STATUS_ACKNOWLEDGE, VIRTIO_STATUS_DRIVER, VIRTIO_STATUS_DRIVER_OK, VIRTIO_STATUS_FAILED, VIRTIO_STATUS_FEATURES_OK};
+use crate::executor::WaitQueue;
 use crate::println;
 use crate::memory;
+use crate::spinlock::SpinLock;
+use crate::sync::Once;
 
 // VirtIO Block Request Type
 const VIRTIO_BLK_T_IN: u32 = 0;
 const VIRTIO_BLK_T_OUT: u32 = 1;
+const VIRTIO_BLK_T_FLUSH: u32 = 4;
+
+// VirtIO Block feature bits
+const VIRTIO_BLK_F_FLUSH: u64 = 1 << 9;
+const VIRTIO_BLK_F_MQ: u64 = 1 << 12;
+
+// Device config offsets (struct virtio_blk_config)
+const VIRTIO_BLK_CFG_CAPACITY: u64 = 0;
+const VIRTIO_BLK_CFG_NUM_QUEUES: u64 = 34;
+
+const BLK_SECTOR_SIZE: usize = 512;
+const BLK_MAX_QUEUES: usize = 4;
+const BLK_QUEUE_SIZE: u16 = 64;
+const BLK_MAX_SECTORS_PER_REQUEST: usize = 256;
+const BLK_REQUEST_TIMEOUT_TICKS: u64 = 1000;
+/// How often the timer wheel reaps the queues while `BlkIo` futures wait.
+const BLK_REAP_MS: u64 = 1;
 
 #[repr(C, packed)]
+#[derive(Clone, Copy)]
 struct VirtioBlkReq {
     type_: u32,
     reserved: u32,
@@ -16,6 +46,7 @@ struct VirtioBlkReq {
 
 // VirtQueue Structures
 #[repr(C, packed)]
+#[derive(Clone, Copy)]
 struct VirtqDesc {
     addr: u64,
     len: u32,
@@ -48,6 +79,7 @@ struct VirtqUsed {
 // Flag constants
 const VRING_DESC_F_NEXT: u16 = 1;
 const VRING_DESC_F_WRITE: u16 = 2;
+const VRING_DESC_F_INDIRECT: u16 = 4;
 
 // Static driver state
 static mut BLOCK_DEVICE: Option<VirtioBlockDriver> = None;
@@ -160,7 +192,583 @@ impl VirtioBlockDriver {
 }
 
 
+// ---------------------------------------------------------------------------
+// Virtio 1.0 transport: per-CPU queues, indirect descriptors and polled
+// asynchronous completion. The legacy driver above stays as a fallback for
+// devices that only expose the I/O window.
+// ---------------------------------------------------------------------------
+
+static MODERN_BLOCK_DEVICE: Once<ModernBlockDriver> = Once::new();
+/// Wakes `BlkIo` futures whenever a poll reaps finished requests.
+static COMPLETED: WaitQueue = WaitQueue::new();
+/// A reap is queued on the timer wheel. The device raises no interrupt we
+/// handle, so waiting futures are served by polling from the timer.
+static REAP_ARMED: AtomicBool = AtomicBool::new(false);
+
+/// Handle for a request submitted with `submit_read` / `submit_write` / `submit_flush`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BlkToken(u64);
+
+pub struct BlkCompletion {
+    pub token: BlkToken,
+    pub ok: bool,
+    /// Sector data for reads; empty for writes and flushes.
+    pub data: Vec<u8>,
+}
+
+/// Per-request DMA state. Boxed so its address stays fixed while the device owns it.
+#[repr(C, align(16))]
+struct BlkRequestSlot {
+    indirect: [VirtqDesc; 3],
+    header: VirtioBlkReq,
+    status: u8,
+}
+
+struct InflightRequest {
+    token: BlkToken,
+    slot: Box<BlkRequestSlot>,
+    data: Vec<u8>,
+    kind: u32,
+    chain: [u16; 3],
+    chain_len: usize,
+}
+
+struct BlkQueue {
+    index: u16,
+    size: u16,
+    desc: *mut VirtqDesc,
+    avail: u64,
+    used: u64,
+    notify_addr: u64,
+    avail_idx: u16,
+    last_used: u16,
+    free: Vec<u16>,
+    inflight: Vec<Option<InflightRequest>>,
+}
+
+// The rings live in DMA frames owned by the device; each queue's `SpinLock`
+// serializes every access to them.
+unsafe impl Send for BlkQueue {}
+
+impl BlkQueue {
+    fn new(transport: &ModernTransport, index: u16, size: u16) -> Option<Self> {
+        let ring_frame = memory::alloc_frame()?;
+        let used_frame = memory::alloc_frame()?;
+        let desc_bytes = size as u64 * 16;
+        unsafe {
+            core::ptr::write_bytes(ring_frame as *mut u8, 0, 4096);
+            core::ptr::write_bytes(used_frame as *mut u8, 0, 4096);
+        }
+        let avail = ring_frame + desc_bytes;
+        let notify_addr = transport.setup_queue(index, size, ring_frame, avail, used_frame);
+        let mut inflight = Vec::with_capacity(size as usize);
+        inflight.resize_with(size as usize, || None);
+        Some(Self {
+            index,
+            size,
+            desc: ring_frame as *mut VirtqDesc,
+            avail,
+            used: used_frame,
+            notify_addr,
+            avail_idx: 0,
+            last_used: 0,
+            free: (0..size).rev().collect(),
+            inflight,
+        })
+    }
+
+    fn free_descriptors(&self) -> usize {
+        self.free.len()
+    }
+
+    unsafe fn submit(
+        &mut self,
+        transport: &ModernTransport,
+        mut req: InflightRequest,
+        use_indirect: bool,
+    ) -> Result<(), InflightRequest> {
+        let count = if req.data.is_empty() { 2 } else { 3 };
+        let needed = if use_indirect { 1 } else { count };
+        if self.free.len() < needed {
+            return Err(req);
+        }
+        for i in 0..needed {
+            req.chain[i] = self.free.pop().unwrap_or(0);
+        }
+        req.chain_len = needed;
+
+        let slot: &mut BlkRequestSlot = req.slot.as_mut();
+        let mut parts: [VirtqDesc; 3] = [VirtqDesc { addr: 0, len: 0, flags: 0, next: 0 }; 3];
+        parts[0] = VirtqDesc {
+            addr: core::ptr::addr_of!(slot.header) as u64,
+            len: core::mem::size_of::<VirtioBlkReq>() as u32,
+            flags: VRING_DESC_F_NEXT,
+            next: 0,
+        };
+        if count == 3 {
+            let device_writes = req.kind == VIRTIO_BLK_T_IN;
+            parts[1] = VirtqDesc {
+                addr: req.data.as_ptr() as u64,
+                len: req.data.len() as u32,
+                flags: VRING_DESC_F_NEXT | if device_writes { VRING_DESC_F_WRITE } else { 0 },
+                next: 0,
+            };
+        }
+        slot.status = 0xFF;
+        parts[count - 1] = VirtqDesc {
+            addr: core::ptr::addr_of!(slot.status) as u64,
+            len: 1,
+            flags: VRING_DESC_F_WRITE,
+            next: 0,
+        };
+
+        if use_indirect {
+            for i in 0..count {
+                if i + 1 < count {
+                    parts[i].next = (i + 1) as u16;
+                }
+                slot.indirect[i] = parts[i];
+            }
+            core::ptr::write_volatile(
+                self.desc.add(req.chain[0] as usize),
+                VirtqDesc {
+                    addr: slot.indirect.as_ptr() as u64,
+                    len: (count * core::mem::size_of::<VirtqDesc>()) as u32,
+                    flags: VRING_DESC_F_INDIRECT,
+                    next: 0,
+                },
+            );
+        } else {
+            for i in 0..count {
+                if i + 1 < count {
+                    parts[i].next = req.chain[i + 1];
+                }
+                core::ptr::write_volatile(self.desc.add(req.chain[i] as usize), parts[i]);
+            }
+        }
+
+        let head = req.chain[0];
+        self.inflight[head as usize] = Some(req);
+        let ring_entry = self.avail + 4 + 2 * (self.avail_idx % self.size) as u64;
+        core::ptr::write_volatile(ring_entry as *mut u16, head);
+        fence(Ordering::SeqCst);
+        self.avail_idx = self.avail_idx.wrapping_add(1);
+        core::ptr::write_volatile((self.avail + 2) as *mut u16, self.avail_idx);
+        fence(Ordering::SeqCst);
+        transport.notify(self.notify_addr, self.index);
+        Ok(())
+    }
+
+    unsafe fn harvest(&mut self, out: &mut Vec<BlkCompletion>) -> usize {
+        let mut harvested = 0;
+        loop {
+            let used_idx = core::ptr::read_volatile((self.used + 2) as *const u16);
+            if used_idx == self.last_used {
+                break;
+            }
+            fence(Ordering::SeqCst);
+            let elem = self.used + 4 + 8 * (self.last_used % self.size) as u64;
+            let id = core::ptr::read_volatile(elem as *const u32) as usize;
+            self.last_used = self.last_used.wrapping_add(1);
+
+            let Some(req) = self.inflight.get_mut(id).and_then(|s| s.take()) else {
+                continue;
+            };
+            for i in 0..req.chain_len {
+                self.free.push(req.chain[i]);
+            }
+            let status = core::ptr::read_volatile(core::ptr::addr_of!(req.slot.status));
+            let data = if req.kind == VIRTIO_BLK_T_IN { req.data } else { Vec::new() };
+            out.push(BlkCompletion { token: req.token, ok: status == 0, data });
+            harvested += 1;
+        }
+        harvested
+    }
+}
+
+/// Set once at probe and shared by every CPU: each queue has its own lock,
+/// so submitters on different queues never wait on each other.
+struct ModernBlockDriver {
+    transport: ModernTransport,
+    queues: Vec<SpinLock<BlkQueue>>,
+    indirect: bool,
+    flush: bool,
+    capacity_sectors: u64,
+    next_token: AtomicU64,
+    completed: SpinLock<Vec<BlkCompletion>>,
+    submitted: AtomicU64,
+    completed_count: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl ModernBlockDriver {
+    fn new(transport: ModernTransport) -> Option<Self> {
+        if !transport.reset() {
+            return None;
+        }
+        transport.add_status(VIRTIO_STATUS_ACKNOWLEDGE);
+        transport.add_status(VIRTIO_STATUS_DRIVER);
+
+        let offered = transport.device_features();
+        if (offered & VIRTIO_F_VERSION_1) == 0 {
+            transport.add_status(VIRTIO_STATUS_FAILED);
+            return None;
+        }
+        let accepted = VIRTIO_F_VERSION_1
+            | (offered & (VIRTIO_RING_F_INDIRECT_DESC | VIRTIO_BLK_F_MQ | VIRTIO_BLK_F_FLUSH));
+        transport.set_driver_features(accepted);
+        transport.add_status(VIRTIO_STATUS_FEATURES_OK);
+        if (transport.status() & VIRTIO_STATUS_FEATURES_OK) == 0 {
+            println("VirtIO Block: device rejected feature set.");
+            transport.add_status(VIRTIO_STATUS_FAILED);
+            return None;
+        }
+
+        let mut wanted_queues = 1usize;
+        if (accepted & VIRTIO_BLK_F_MQ) != 0 {
+            wanted_queues = (transport.device_read16(VIRTIO_BLK_CFG_NUM_QUEUES) as usize).max(1);
+        }
+        wanted_queues = wanted_queues
+            .min(transport.num_queues().max(1) as usize)
+            .min(BLK_MAX_QUEUES)
+            .min((crate::smp::cpu_count() as usize).max(1));
+
+        let mut queues = Vec::new();
+        for index in 0..wanted_queues as u16 {
+            let max = transport.queue_max_size(index);
+            if max == 0 {
+                break;
+            }
+            match BlkQueue::new(&transport, index, max.min(BLK_QUEUE_SIZE)) {
+                Some(queue) => queues.push(SpinLock::new(queue)),
+                None => break,
+            }
+        }
+        if queues.is_empty() {
+            transport.add_status(VIRTIO_STATUS_FAILED);
+            return None;
+        }
+
+        transport.add_status(VIRTIO_STATUS_DRIVER_OK);
+        Some(Self {
+            capacity_sectors: transport.device_read64(VIRTIO_BLK_CFG_CAPACITY),
+            transport,
+            queues,
+            indirect: (accepted & VIRTIO_RING_F_INDIRECT_DESC) != 0,
+            flush: (accepted & VIRTIO_BLK_F_FLUSH) != 0,
+            next_token: AtomicU64::new(1),
+            completed: SpinLock::new(Vec::new()),
+            submitted: AtomicU64::new(0),
+            completed_count: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+        })
+    }
+
+    fn submit(&self, kind: u32, sector: u64, data: Vec<u8>) -> Option<BlkToken> {
+        let token = BlkToken(self.next_token.fetch_add(1, Ordering::Relaxed));
+        let mut req = InflightRequest {
+            token,
+            slot: Box::new(BlkRequestSlot {
+                indirect: [VirtqDesc { addr: 0, len: 0, flags: 0, next: 0 }; 3],
+                header: VirtioBlkReq { type_: kind, reserved: 0, sector },
+                status: 0xFF,
+            }),
+            data,
+            kind,
+            chain: [0; 3],
+            chain_len: 0,
+        };
+
+        // Each CPU starts on its own queue so submitters do not contend for
+        // its lock; a full queue passes the request to the next one.
+        let first = crate::smp::current_cpu_index() % self.queues.len();
+        for attempt in 0..2 {
+            for step in 0..self.queues.len() {
+                let mut queue = self.queues[(first + step) % self.queues.len()].lock();
+                if queue.free_descriptors() == 0 {
+                    continue;
+                }
+                match unsafe { queue.submit(&self.transport, req, self.indirect) } {
+                    Ok(()) => {
+                        self.submitted.fetch_add(1, Ordering::Relaxed);
+                        return Some(token);
+                    }
+                    Err(back) => req = back,
+                }
+            }
+            if attempt == 0 {
+                self.poll();
+            }
+        }
+        None
+    }
+
+    fn poll(&self) -> usize {
+        let mut done = Vec::new();
+        for queue in self.queues.iter() {
+            unsafe {
+                queue.lock().harvest(&mut done);
+            }
+        }
+        let count = done.len();
+        if count > 0 {
+            COMPLETED.wake_all();
+        }
+        let errors = done.iter().filter(|c| !c.ok).count();
+        self.completed_count.fetch_add(count as u64, Ordering::Relaxed);
+        self.errors.fetch_add(errors as u64, Ordering::Relaxed);
+        let mut completed = self.completed.lock();
+        completed.extend(done);
+        // Completions nobody claimed (e.g. after a blocking caller timed out) are dropped.
+        if completed.len() > BLK_QUEUE_SIZE as usize * BLK_MAX_QUEUES {
+            let excess = completed.len() - BLK_QUEUE_SIZE as usize * BLK_MAX_QUEUES;
+            completed.drain(0..excess);
+        }
+        count
+    }
+
+    fn take(&self, token: BlkToken) -> Option<BlkCompletion> {
+        let mut completed = self.completed.lock();
+        let pos = completed.iter().position(|c| c.token == token)?;
+        Some(completed.remove(pos))
+    }
+
+    fn wait(&self, token: BlkToken) -> Option<BlkCompletion> {
+        let start = crate::timer::ticks();
+        loop {
+            self.poll();
+            if let Some(done) = self.take(token) {
+                return Some(done);
+            }
+            if crate::timer::ticks().wrapping_sub(start) > BLK_REQUEST_TIMEOUT_TICKS {
+                println("VirtIO Block: Request Timeout!");
+                return None;
+            }
+            core::hint::spin_loop();
+        }
+    }
+
+    fn read_blocking(&self, sector: u64, buffer: &mut [u8]) -> bool {
+        let sectors = buffer.len().div_ceil(BLK_SECTOR_SIZE).clamp(1, BLK_MAX_SECTORS_PER_REQUEST);
+        let Some(token) = self.submit(VIRTIO_BLK_T_IN, sector, alloc::vec![0u8; sectors * BLK_SECTOR_SIZE]) else {
+            return false;
+        };
+        match self.wait(token) {
+            Some(done) if done.ok => {
+                let n = buffer.len().min(done.data.len());
+                buffer[..n].copy_from_slice(&done.data[..n]);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn write_blocking(&self, sector: u64, buffer: &[u8]) -> bool {
+        let sectors = buffer.len().div_ceil(BLK_SECTOR_SIZE).clamp(1, BLK_MAX_SECTORS_PER_REQUEST);
+        let mut data = alloc::vec![0u8; sectors * BLK_SECTOR_SIZE];
+        let n = buffer.len().min(data.len());
+        data[..n].copy_from_slice(&buffer[..n]);
+        let Some(token) = self.submit(VIRTIO_BLK_T_OUT, sector, data) else {
+            return false;
+        };
+        matches!(self.wait(token), Some(done) if done.ok)
+    }
+
+    fn status_line(&self) -> String {
+        alloc::format!(
+            "VirtIO Block: virtio 1.0, {} MiB, colas={} indirect={} flush={} enviadas={} completadas={} errores={}",
+            self.capacity_sectors * BLK_SECTOR_SIZE as u64 / (1024 * 1024),
+            self.queues.len(),
+            if self.indirect { "si" } else { "no" },
+            if self.flush { "si" } else { "no" },
+            self.submitted.load(Ordering::Relaxed),
+            self.completed_count.load(Ordering::Relaxed),
+            self.errors.load(Ordering::Relaxed)
+        )
+    }
+}
+
+/// Queue an asynchronous read of `sectors` sectors. Modern transport only.
+pub fn submit_read(lba: u64, sectors: usize) -> Option<BlkToken> {
+    let sectors = sectors.clamp(1, BLK_MAX_SECTORS_PER_REQUEST);
+    let driver = MODERN_BLOCK_DEVICE.get()?;
+    driver.submit(VIRTIO_BLK_T_IN, lba, alloc::vec![0u8; sectors * BLK_SECTOR_SIZE])
+}
+
+/// Queue an asynchronous write; `data` is padded to a whole number of sectors.
+pub fn submit_write(lba: u64, data: &[u8]) -> Option<BlkToken> {
+    let driver = MODERN_BLOCK_DEVICE.get()?;
+    let sectors = data.len().div_ceil(BLK_SECTOR_SIZE).clamp(1, BLK_MAX_SECTORS_PER_REQUEST);
+    let mut buf = alloc::vec![0u8; sectors * BLK_SECTOR_SIZE];
+    let n = data.len().min(buf.len());
+    buf[..n].copy_from_slice(&data[..n]);
+    driver.submit(VIRTIO_BLK_T_OUT, lba, buf)
+}
+
+pub fn submit_flush() -> Option<BlkToken> {
+    let driver = MODERN_BLOCK_DEVICE.get()?;
+    if !driver.flush {
+        return None;
+    }
+    driver.submit(VIRTIO_BLK_T_FLUSH, 0, Vec::new())
+}
+
+/// Reap finished requests from every queue. Returns how many completed.
+pub fn poll_completions() -> usize {
+    MODERN_BLOCK_DEVICE.get().map(|d| d.poll()).unwrap_or(0)
+}
+
+pub fn take_completion(token: BlkToken) -> Option<BlkCompletion> {
+    MODERN_BLOCK_DEVICE.get()?.take(token)
+}
+
+fn arm_reap() {
+    if !REAP_ARMED.swap(true, Ordering::AcqRel) {
+        crate::timer::after_ms(BLK_REAP_MS, reap, 0);
+    }
+}
+
+fn reap(_: usize) {
+    REAP_ARMED.store(false, Ordering::Release);
+    poll_completions();
+    if !COMPLETED.is_empty() {
+        arm_reap();
+    }
+}
+
+/// Future for a submitted request, resolving to its completion, or `None`
+/// after the same timeout the blocking calls use. Dropping it does not
+/// cancel the request; its completion is discarded with the other
+/// unclaimed ones.
+pub struct BlkIo {
+    token: Option<BlkToken>,
+    start_tick: u64,
+}
+
+impl BlkIo {
+    fn new(token: BlkToken) -> Self {
+        Self {
+            token: Some(token),
+            start_tick: crate::timer::ticks(),
+        }
+    }
+}
+
+impl Future for BlkIo {
+    type Output = Option<BlkCompletion>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let Some(token) = self.token else {
+            return Poll::Ready(None);
+        };
+        poll_completions();
+        if let Some(done) = take_completion(token) {
+            self.token = None;
+            return Poll::Ready(Some(done));
+        }
+        if crate::timer::ticks().wrapping_sub(self.start_tick) > BLK_REQUEST_TIMEOUT_TICKS {
+            self.token = None;
+            println("VirtIO Block: Request Timeout!");
+            return Poll::Ready(None);
+        }
+        COMPLETED.register(cx.waker());
+        arm_reap();
+        Poll::Pending
+    }
+}
+
+/// `submit_read` as a future for executor tasks.
+pub fn read_async(lba: u64, sectors: usize) -> Option<BlkIo> {
+    submit_read(lba, sectors).map(BlkIo::new)
+}
+
+/// `submit_write` as a future for executor tasks.
+pub fn write_async(lba: u64, data: &[u8]) -> Option<BlkIo> {
+    submit_write(lba, data).map(BlkIo::new)
+}
+
+/// `submit_flush` as a future for executor tasks.
+pub fn flush_async() -> Option<BlkIo> {
+    submit_flush().map(BlkIo::new)
+}
+
+/// Multi-sector synchronous read; `buffer.len()` should be a multiple of 512.
+pub fn read_blocks(lba: u64, buffer: &mut [u8]) -> bool {
+    if let Some(driver) = MODERN_BLOCK_DEVICE.get() {
+        for (i, chunk) in buffer.chunks_mut(BLK_MAX_SECTORS_PER_REQUEST * BLK_SECTOR_SIZE).enumerate() {
+            let sector = lba + (i * BLK_MAX_SECTORS_PER_REQUEST) as u64;
+            if !driver.read_blocking(sector, chunk) {
+                return false;
+            }
+        }
+        return true;
+    }
+    for (i, chunk) in buffer.chunks_mut(BLK_SECTOR_SIZE).enumerate() {
+        let mut sector = [0u8; BLK_SECTOR_SIZE];
+        if !read(lba + i as u64, &mut sector) {
+            return false;
+        }
+        chunk.copy_from_slice(&sector[..chunk.len()]);
+    }
+    true
+}
+
+/// Multi-sector synchronous write; a trailing partial sector is zero padded.
+pub fn write_blocks(lba: u64, buffer: &[u8]) -> bool {
+    if let Some(driver) = MODERN_BLOCK_DEVICE.get() {
+        for (i, chunk) in buffer.chunks(BLK_MAX_SECTORS_PER_REQUEST * BLK_SECTOR_SIZE).enumerate() {
+            let sector = lba + (i * BLK_MAX_SECTORS_PER_REQUEST) as u64;
+            if !driver.write_blocking(sector, chunk) {
+                return false;
+            }
+        }
+        return true;
+    }
+    for (i, chunk) in buffer.chunks(BLK_SECTOR_SIZE).enumerate() {
+        let mut sector = [0u8; BLK_SECTOR_SIZE];
+        sector[..chunk.len()].copy_from_slice(chunk);
+        if !write(lba + i as u64, &sector) {
+            return false;
+        }
+    }
+    true
+}
+
+pub fn capacity_sectors() -> u64 {
+    MODERN_BLOCK_DEVICE.get().map(|d| d.capacity_sectors).unwrap_or(0)
+}
+
+pub fn status_line() -> String {
+    if let Some(driver) = MODERN_BLOCK_DEVICE.get() {
+        return driver.status_line();
+    }
+    unsafe {
+        if BLOCK_DEVICE.is_some() {
+            return String::from("VirtIO Block: transporte legacy, 1 cola sincrona.");
+        }
+    }
+    String::from("VirtIO Block: sin dispositivo.")
+}
+
 pub fn init(pci_dev: PciDevice) {
+    // Transitional devices expose both transports; prefer the modern one.
+    if let Some(transport) = ModernTransport::probe(pci_dev) {
+        match ModernBlockDriver::new(transport) {
+            Some(driver) => {
+                println(driver.status_line().as_str());
+                if MODERN_BLOCK_DEVICE.set(driver).is_err() {
+                    println("VirtIO Block: a modern device is already bound; ignoring this one.");
+                    return;
+                }
+                let node = crate::device::add_class_device(
+                    Some(crate::device::pci_function(&pci_dev)), "vd", "block", "virtio-blk");
+                crate::device::set_live(node, "size", || alloc::format!("{}", capacity_sectors()));
+                return;
+            }
+            None => println("VirtIO Block: modern transport setup failed, trying legacy."),
+        }
+    }
+
     if let Some(dev) = VirtioDevice::new(pci_dev) {
         println("VirtIO Block: Found.");
         
@@ -303,6 +911,8 @@ pub fn init(pci_dev: PciDevice) {
                  driver.dev.add_status(VIRTIO_STATUS_DRIVER_OK);
                  
                  BLOCK_DEVICE = Some(driver);
+                 crate::device::add_class_device(
+                     Some(crate::device::pci_function(&pci_dev)), "vd", "block", "virtio-blk");
                  println("VirtIO Block: Initialized & Ready.");
              }
         } else {
@@ -310,7 +920,14 @@ pub fn init(pci_dev: PciDevice) {
         }
     }
 }
+pub fn is_ready() -> bool {
+    MODERN_BLOCK_DEVICE.is_set() || unsafe { BLOCK_DEVICE.is_some() }
+}
+
 pub fn write(lba: u64, buffer: &[u8]) -> bool {
+    if let Some(driver) = MODERN_BLOCK_DEVICE.get() {
+        return driver.write_blocking(lba, &buffer[..buffer.len().min(BLK_SECTOR_SIZE)]);
+    }
     unsafe {
         if let Some(driver) = &mut BLOCK_DEVICE {
             return driver.write_sector(lba, buffer);
@@ -320,6 +937,10 @@ pub fn write(lba: u64, buffer: &[u8]) -> bool {
 }
 
 pub fn read(lba: u64, buffer: &mut [u8]) -> bool {
+    if let Some(driver) = MODERN_BLOCK_DEVICE.get() {
+        let len = buffer.len().min(BLK_SECTOR_SIZE);
+        return driver.read_blocking(lba, &mut buffer[..len]);
+    }
     unsafe {
         if let Some(driver) = &mut BLOCK_DEVICE {
             // println("VirtIO Block: Reading LBA...");
@@ -1,6 +1,418 @@
+//! virtio-input driver (virtio spec 5.8) for keyboards, mice and tablets.
+//!
+//! QEMU's `virtio-tablet-pci` reports absolute coordinates, so the cursor lands
+//! exactly where the host pointer is instead of drifting with UEFI pointer
+//! polling. Events are decoded per EV_SYN report and handed to `crate::input`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{fence, Ordering};
+
+use crate::memory;
 use crate::pci::PciDevice;
 use crate::println;
+use crate::spinlock::SpinLock;
+use crate::virtio::modern::{ModernTransport, VIRTIO_F_VERSION_1};
+use crate::virtio::queue::{VirtqDesc, VRING_DESC_F_WRITE};
+use crate::virtio::{
+    VIRTIO_STATUS_ACKNOWLEDGE, VIRTIO_STATUS_DRIVER, VIRTIO_STATUS_DRIVER_OK,
+    VIRTIO_STATUS_FAILED, VIRTIO_STATUS_FEATURES_OK,
+};
+
+// struct virtio_input_config
+const CFG_SELECT: u64 = 0;
+const CFG_SUBSEL: u64 = 1;
+const CFG_SIZE: u64 = 2;
+const CFG_UNION: u64 = 8;
+
+const VIRTIO_INPUT_CFG_ID_NAME: u8 = 0x01;
+const VIRTIO_INPUT_CFG_EV_BITS: u8 = 0x11;
+const VIRTIO_INPUT_CFG_ABS_INFO: u8 = 0x12;
+
+// Linux input event codes (include/uapi/linux/input-event-codes.h).
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_WHEEL: u16 = 0x08;
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_TOUCH: u16 = 0x14A;
+
+const EVENT_QUEUE: u16 = 0;
+const EVENT_QUEUE_MAX: u16 = 64;
+const MAX_INPUT_DEVICES: usize = 4;
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct VirtioInputEvent {
+    kind: u16,
+    code: u16,
+    value: u32,
+}
+
+#[derive(Clone, Copy)]
+struct AbsRange {
+    min: i32,
+    max: i32,
+}
+
+impl AbsRange {
+    fn scale(&self, raw: i32, pixels: u32) -> i32 {
+        let span = (self.max as i64 - self.min as i64).max(1);
+        let pos = (raw as i64 - self.min as i64).clamp(0, span);
+        (pos * (pixels.saturating_sub(1)) as i64 / span) as i32
+    }
+}
+
+/// Pointer state accumulated between two EV_SYN events.
+#[derive(Clone, Copy, Default)]
+struct PendingReport {
+    dx: i32,
+    dy: i32,
+    wheel: i32,
+    abs_x: Option<i32>,
+    abs_y: Option<i32>,
+    dirty: bool,
+}
+
+struct EventQueue {
+    size: u16,
+    desc: *mut VirtqDesc,
+    avail: u64,
+    used: u64,
+    notify_addr: u64,
+    avail_idx: u16,
+    last_used: u16,
+    events: Vec<VirtioInputEvent>,
+}
+
+impl EventQueue {
+    fn new(transport: &ModernTransport, size: u16) -> Option<Self> {
+        let ring_frame = memory::alloc_frame()?;
+        let used_frame = memory::alloc_frame()?;
+        unsafe {
+            core::ptr::write_bytes(ring_frame as *mut u8, 0, 4096);
+            core::ptr::write_bytes(used_frame as *mut u8, 0, 4096);
+        }
+        let avail = ring_frame + size as u64 * 16;
+        let notify_addr = transport.setup_queue(EVENT_QUEUE, size, ring_frame, avail, used_frame);
+        let mut queue = Self {
+            size,
+            desc: ring_frame as *mut VirtqDesc,
+            avail,
+            used: used_frame,
+            notify_addr,
+            avail_idx: 0,
+            last_used: 0,
+            events: alloc::vec![VirtioInputEvent::default(); size as usize],
+        };
+        // The event queue is device-writable only: every descriptor owns one
+        // event slot and is handed straight back after it has been read.
+        for i in 0..size {
+            unsafe {
+                core::ptr::write_volatile(
+                    queue.desc.add(i as usize),
+                    VirtqDesc {
+                        addr: queue.events.as_ptr().add(i as usize) as u64,
+                        len: core::mem::size_of::<VirtioInputEvent>() as u32,
+                        flags: VRING_DESC_F_WRITE,
+                        next: 0,
+                    },
+                );
+            }
+            queue.post(i);
+        }
+        transport.notify(queue.notify_addr, EVENT_QUEUE);
+        Some(queue)
+    }
+
+    fn post(&mut self, desc: u16) {
+        unsafe {
+            let ring_entry = self.avail + 4 + 2 * (self.avail_idx % self.size) as u64;
+            core::ptr::write_volatile(ring_entry as *mut u16, desc);
+            fence(Ordering::SeqCst);
+            self.avail_idx = self.avail_idx.wrapping_add(1);
+            core::ptr::write_volatile((self.avail + 2) as *mut u16, self.avail_idx);
+        }
+    }
+
+    /// Pop every event the device has written, returning the slots to the ring.
+    fn drain(&mut self, transport: &ModernTransport, out: &mut Vec<VirtioInputEvent>) {
+        let mut reposted = false;
+        loop {
+            let used_idx = unsafe { core::ptr::read_volatile((self.used + 2) as *const u16) };
+            if used_idx == self.last_used {
+                break;
+            }
+            fence(Ordering::SeqCst);
+            let elem = self.used + 4 + 8 * (self.last_used % self.size) as u64;
+            let id = unsafe { core::ptr::read_volatile(elem as *const u32) } as u16;
+            self.last_used = self.last_used.wrapping_add(1);
+            if id >= self.size {
+                continue;
+            }
+            let event = unsafe { core::ptr::read_volatile(self.events.as_ptr().add(id as usize)) };
+            out.push(event);
+            self.post(id);
+            reposted = true;
+        }
+        if reposted {
+            fence(Ordering::SeqCst);
+            transport.notify(self.notify_addr, EVENT_QUEUE);
+        }
+    }
+}
+
+struct VirtioInputDevice {
+    name: String,
+    transport: ModernTransport,
+    queue: EventQueue,
+    abs_x: Option<AbsRange>,
+    abs_y: Option<AbsRange>,
+    has_keys: bool,
+    has_rel: bool,
+    pending: PendingReport,
+    left: bool,
+    right: bool,
+    events: u64,
+}
+
+// The event ring lives in DMA frames owned by the device; the `SpinLock`
+// around `INPUT_DEVICES` serializes every access to it.
+unsafe impl Send for EventQueue {}
+
+static INPUT_DEVICES: SpinLock<Vec<VirtioInputDevice>> = SpinLock::new(Vec::new());
+
+fn config_select(transport: &ModernTransport, select: u8, subsel: u8) -> u8 {
+    transport.device_write8(CFG_SELECT, select);
+    transport.device_write8(CFG_SUBSEL, subsel);
+    transport.device_read8(CFG_SIZE)
+}
+
+fn read_name(transport: &ModernTransport) -> String {
+    let size = config_select(transport, VIRTIO_INPUT_CFG_ID_NAME, 0).min(128);
+    let mut name = String::new();
+    for i in 0..size as u64 {
+        let byte = transport.device_read8(CFG_UNION + i);
+        if byte == 0 {
+            break;
+        }
+        name.push(if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '?' });
+    }
+    if name.is_empty() {
+        name.push_str("virtio-input");
+    }
+    name
+}
+
+fn supports_event_type(transport: &ModernTransport, kind: u16) -> bool {
+    config_select(transport, VIRTIO_INPUT_CFG_EV_BITS, kind as u8) != 0
+}
+
+fn read_abs_range(transport: &ModernTransport, axis: u16) -> Option<AbsRange> {
+    if config_select(transport, VIRTIO_INPUT_CFG_ABS_INFO, axis as u8) == 0 {
+        return None;
+    }
+    let min = transport.device_read32(CFG_UNION) as i32;
+    let max = transport.device_read32(CFG_UNION + 4) as i32;
+    if max <= min {
+        return None;
+    }
+    Some(AbsRange { min, max })
+}
+
+pub fn init(device: PciDevice) {
+    if INPUT_DEVICES.lock().len() >= MAX_INPUT_DEVICES {
+        println("VirtIO input: too many devices, ignoring.");
+        return;
+    }
+    let Some(transport) = ModernTransport::probe(device) else {
+        println("VirtIO input: missing virtio 1.0 capabilities.");
+        return;
+    };
+    if !transport.has_device_config() || !transport.reset() {
+        println("VirtIO input: device reset failed.");
+        return;
+    }
+    transport.add_status(VIRTIO_STATUS_ACKNOWLEDGE);
+    transport.add_status(VIRTIO_STATUS_DRIVER);
+
+    if transport.device_features() & VIRTIO_F_VERSION_1 == 0 {
+        transport.add_status(VIRTIO_STATUS_FAILED);
+        println("VirtIO input: device does not offer VERSION_1.");
+        return;
+    }
+    transport.set_driver_features(VIRTIO_F_VERSION_1);
+    transport.add_status(VIRTIO_STATUS_FEATURES_OK);
+    if transport.status() & VIRTIO_STATUS_FEATURES_OK == 0 {
+        transport.add_status(VIRTIO_STATUS_FAILED);
+        println("VirtIO input: FEATURES_OK rejected.");
+        return;
+    }
+
+    let name = read_name(&transport);
+    let abs_x = read_abs_range(&transport, ABS_X);
+    let abs_y = read_abs_range(&transport, ABS_Y);
+    let has_keys = supports_event_type(&transport, EV_KEY);
+    let has_rel = supports_event_type(&transport, EV_REL);
+
+    let size = transport.queue_max_size(EVENT_QUEUE).min(EVENT_QUEUE_MAX);
+    if size == 0 {
+        transport.add_status(VIRTIO_STATUS_FAILED);
+        println("VirtIO input: event queue unavailable.");
+        return;
+    }
+    let Some(queue) = EventQueue::new(&transport, size) else {
+        transport.add_status(VIRTIO_STATUS_FAILED);
+        println("VirtIO input: out of memory for event queue.");
+        return;
+    };
+    transport.add_status(VIRTIO_STATUS_DRIVER_OK);
+
+    println(&alloc::format!(
+        "VirtIO input: {} ({}{}{})",
+        name,
+        if abs_x.is_some() && abs_y.is_some() { "tablet " } else { "" },
+        if has_rel { "mouse " } else { "" },
+        if has_keys { "keys" } else { "" }
+    ));
+
+    let node = crate::device::add_class_device(Some(crate::device::pci_function(&device)), "input", "input", "virtio-input");
+    crate::device::set_attr(node, "name", name.as_str());
+    let mut caps = Vec::new();
+    if abs_x.is_some() && abs_y.is_some() {
+        caps.push("abs");
+    }
+    if has_rel {
+        caps.push("rel");
+    }
+    if has_keys {
+        caps.push("key");
+    }
+    crate::device::set_attr(node, "capabilities", caps.join(" ").as_str());
+
+    INPUT_DEVICES.lock().push(VirtioInputDevice {
+        name,
+        transport,
+        queue,
+        abs_x,
+        abs_y,
+        has_keys,
+        has_rel,
+        pending: PendingReport::default(),
+        left: false,
+        right: false,
+        events: 0,
+    });
+}
+
+pub fn is_present() -> bool {
+    !INPUT_DEVICES.lock().is_empty()
+}
+
+impl VirtioInputDevice {
+    fn handle(&mut self, event: VirtioInputEvent) {
+        self.events = self.events.saturating_add(1);
+        match event.kind {
+            EV_REL => {
+                let delta = event.value as i32;
+                match event.code {
+                    REL_X => self.pending.dx = self.pending.dx.saturating_add(delta),
+                    REL_Y => self.pending.dy = self.pending.dy.saturating_add(delta),
+                    REL_WHEEL => self.pending.wheel = self.pending.wheel.saturating_add(delta),
+                    _ => return,
+                }
+                self.pending.dirty = true;
+            }
+            EV_ABS => {
+                let (width, height) = crate::input::screen_dimensions();
+                let raw = event.value as i32;
+                match (event.code, self.abs_x, self.abs_y) {
+                    (ABS_X, Some(range), _) => self.pending.abs_x = Some(range.scale(raw, width)),
+                    (ABS_Y, _, Some(range)) => self.pending.abs_y = Some(range.scale(raw, height)),
+                    _ => return,
+                }
+                self.pending.dirty = true;
+            }
+            EV_KEY => match event.code {
+                BTN_LEFT | BTN_TOUCH => {
+                    self.left = event.value != 0;
+                    self.pending.dirty = true;
+                }
+                BTN_RIGHT => {
+                    self.right = event.value != 0;
+                    self.pending.dirty = true;
+                }
+                code => crate::input::push_virtio_key(code, event.value),
+            },
+            EV_SYN => {
+                if !self.pending.dirty {
+                    return;
+                }
+                let pending = core::mem::take(&mut self.pending);
+                let absolute = match (pending.abs_x, pending.abs_y) {
+                    (None, None) => None,
+                    (x, y) => {
+                        let (last_x, last_y) = crate::input::virtio_absolute_position();
+                        Some((x.unwrap_or(last_x), y.unwrap_or(last_y)))
+                    }
+                };
+                crate::input::push_virtio_pointer(crate::input::VirtioPointerReport {
+                    dx: pending.dx,
+                    dy: pending.dy,
+                    wheel: pending.wheel,
+                    left: self.left,
+                    right: self.right,
+                    absolute,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Drain the event queues of every virtio-input device. Cheap when idle.
+pub fn poll() {
+    let mut devices = INPUT_DEVICES.lock();
+    if devices.is_empty() {
+        return;
+    }
+    let mut events = Vec::new();
+    for dev in devices.iter_mut() {
+        events.clear();
+        dev.queue.drain(&dev.transport, &mut events);
+        for event in events.iter() {
+            dev.handle(*event);
+        }
+    }
+}
 
-pub fn init(_device: PciDevice) {
-    println("VirtIO Input Driver Initialized (Stub)");
+pub fn status_lines() -> Vec<String> {
+    let devices = INPUT_DEVICES.lock();
+    let mut out = Vec::new();
+    for (i, dev) in devices.iter().enumerate() {
+        let mut kinds = Vec::new();
+        if let (Some(x), Some(y)) = (dev.abs_x, dev.abs_y) {
+            kinds.push(alloc::format!("tablet X[{}..{}] Y[{}..{}]", x.min, x.max, y.min, y.max));
+        }
+        if dev.has_rel {
+            kinds.push(String::from("mouse"));
+        }
+        if dev.has_keys {
+            kinds.push(String::from("teclas"));
+        }
+        out.push(alloc::format!(
+            "virtio-input{}: {} [{}] eventos={}",
+            i,
+            dev.name,
+            kinds.join(", "),
+            dev.events
+        ));
+    }
+    out
 }
@@ -1,10 +1,12 @@
 use crate::hal::{inb, inl, outb, outl, outw, inw};
-use crate::pci::{self, PciDevice};
+use crate::pci::{self, PciDevice, PciDriver, PciMatch};
 use crate::println;
 
 pub mod block;
-mod input;
+pub mod input;
+pub mod modern;
 pub mod net;
+pub mod p9;
 pub mod queue;
 
 // Legacy VirtIO Header Offsets (IO Space)
@@ -91,23 +93,28 @@ impl VirtioDevice {
     }
 }
 
-pub fn probe(device: PciDevice) {
-    if device.device_id >= 0x1040 {
-        let _legacy_id = device.device_id - 0x1040 + 0x1000;
-        println("VirtIO: Modern device found (unsupported).");
-        // If we forced legacy, it should have a legacy ID (0x1000-0x103F).
-        // If it still shows up as 0x1040+, it means it's strictly modern or transitional.
-        // QEMU disable-modern=on should force ID 0x1000..
-    }
+pub static PCI_DRIVER: PciDriver = PciDriver {
+    name: "virtio",
+    ids: &[PciMatch::vendor(0x1AF4)],
+    probe,
+};
 
+pub fn probe(device: PciDevice) {
     match device.device_id {
-        0x1001 => block::init(device),
+        // Transitional (0x1001) and modern-only (0x1042) block devices; block::init
+        // picks the virtio 1.0 transport when the capabilities are present.
+        0x1001 | 0x1042 if crate::safemode::active() => {
+            println("VirtIO: safe mode, block device left to UEFI BlockIO.");
+        }
+        0x1001 | 0x1042 => block::init(device),
         0x1000 => net::init(device),
-        0x1002 => input::init(device),
+        0x1009 | 0x1049 => p9::init(device),
+        // virtio-input has no transitional ID; keyboards, mice and tablets all share 0x1052.
+        0x1052 => input::init(device),
         _ => {
             // Check if it's a transitional device with a different ID?
             // Usually 0x1000-0x103F are the ones we care about for legacy I/O.
-             println("Unknown VirtIO device ID (legacy)");
+             println("VirtIO: device ID without driver.");
         }
     }
 }
@@ -0,0 +1,240 @@
+//! Virtio 1.0 PCI transport.
+//!
+//! Modern and transitional devices describe their register blocks with vendor
+//! specific PCI capabilities (virtio spec 4.1.4) that point into memory BARs,
+//! instead of the fixed legacy I/O window used by `VirtioDevice`.
+
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::pci::{self, PciDevice};
+
+const PCI_CAP_ID_VENDOR: u8 = 0x09;
+
+const VIRTIO_PCI_CAP_COMMON_CFG: u8 = 1;
+const VIRTIO_PCI_CAP_NOTIFY_CFG: u8 = 2;
+const VIRTIO_PCI_CAP_ISR_CFG: u8 = 3;
+const VIRTIO_PCI_CAP_DEVICE_CFG: u8 = 4;
+
+// struct virtio_pci_common_cfg
+const COMMON_DEVICE_FEATURE_SELECT: u64 = 0x00;
+const COMMON_DEVICE_FEATURE: u64 = 0x04;
+const COMMON_DRIVER_FEATURE_SELECT: u64 = 0x08;
+const COMMON_DRIVER_FEATURE: u64 = 0x0C;
+const COMMON_NUM_QUEUES: u64 = 0x12;
+const COMMON_DEVICE_STATUS: u64 = 0x14;
+const COMMON_QUEUE_SELECT: u64 = 0x16;
+const COMMON_QUEUE_SIZE: u64 = 0x18;
+const COMMON_QUEUE_MSIX_VECTOR: u64 = 0x1A;
+const COMMON_QUEUE_ENABLE: u64 = 0x1C;
+const COMMON_QUEUE_NOTIFY_OFF: u64 = 0x1E;
+const COMMON_QUEUE_DESC: u64 = 0x20;
+const COMMON_QUEUE_DRIVER: u64 = 0x28;
+const COMMON_QUEUE_DEVICE: u64 = 0x30;
+
+const VIRTIO_MSI_NO_VECTOR: u16 = 0xFFFF;
+
+pub const VIRTIO_F_VERSION_1: u64 = 1 << 32;
+pub const VIRTIO_RING_F_INDIRECT_DESC: u64 = 1 << 28;
+
+pub struct ModernTransport {
+    common: u64,
+    notify_base: u64,
+    notify_multiplier: u32,
+    isr: u64,
+    device_cfg: u64,
+}
+
+impl ModernTransport {
+    /// Locate the virtio capabilities of `pci_dev`. Returns `None` for legacy-only devices.
+    pub fn probe(pci_dev: PciDevice) -> Option<Self> {
+        let (bus, slot, func) = (pci_dev.bus, pci_dev.slot, pci_dev.func);
+        let mut common = None;
+        let mut notify = None;
+        let mut isr = None;
+        let mut device_cfg = None;
+
+        for (cap_id, offset) in pci::capabilities(bus, slot, func) {
+            if cap_id != PCI_CAP_ID_VENDOR {
+                continue;
+            }
+            let header = unsafe { pci::read_config(bus, slot, func, offset) };
+            let cfg_type = ((header >> 24) & 0xFF) as u8;
+            let bar = (unsafe { pci::read_config(bus, slot, func, offset + 4) } & 0xFF) as u8;
+            let bar_offset = unsafe { pci::read_config(bus, slot, func, offset + 8) } as u64;
+            if bar > 5 {
+                continue;
+            }
+            let bar_raw = unsafe { pci::read_config(bus, slot, func, 0x10 + bar * 4) };
+            if (bar_raw & 1) != 0 {
+                // Capability lives in an I/O BAR; only memory BARs are supported here.
+                continue;
+            }
+            let Some(base) = (unsafe { pci::read_bar(bus, slot, func, bar) }) else {
+                continue;
+            };
+            if base == 0 {
+                continue;
+            }
+            let addr = base + bar_offset;
+            match cfg_type {
+                VIRTIO_PCI_CAP_COMMON_CFG if common.is_none() => common = Some(addr),
+                VIRTIO_PCI_CAP_NOTIFY_CFG if notify.is_none() => {
+                    let multiplier = unsafe { pci::read_config(bus, slot, func, offset + 16) };
+                    notify = Some((addr, multiplier));
+                }
+                VIRTIO_PCI_CAP_ISR_CFG if isr.is_none() => isr = Some(addr),
+                VIRTIO_PCI_CAP_DEVICE_CFG if device_cfg.is_none() => device_cfg = Some(addr),
+                _ => {}
+            }
+        }
+
+        let (notify_base, notify_multiplier) = notify?;
+        unsafe {
+            pci::enable_memory_space(bus, slot, func);
+            pci::enable_bus_master(bus, slot, func);
+        }
+        Some(Self {
+            common: common?,
+            notify_base,
+            notify_multiplier,
+            isr: isr?,
+            device_cfg: device_cfg.unwrap_or(0),
+        })
+    }
+
+    fn common_read8(&self, offset: u64) -> u8 {
+        unsafe { read_volatile((self.common + offset) as *const u8) }
+    }
+
+    fn common_read16(&self, offset: u64) -> u16 {
+        unsafe { read_volatile((self.common + offset) as *const u16) }
+    }
+
+    fn common_read32(&self, offset: u64) -> u32 {
+        unsafe { read_volatile((self.common + offset) as *const u32) }
+    }
+
+    fn common_write8(&self, offset: u64, value: u8) {
+        unsafe { write_volatile((self.common + offset) as *mut u8, value) }
+    }
+
+    fn common_write16(&self, offset: u64, value: u16) {
+        unsafe { write_volatile((self.common + offset) as *mut u16, value) }
+    }
+
+    fn common_write32(&self, offset: u64, value: u32) {
+        unsafe { write_volatile((self.common + offset) as *mut u32, value) }
+    }
+
+    fn common_write64(&self, offset: u64, value: u64) {
+        self.common_write32(offset, value as u32);
+        self.common_write32(offset + 4, (value >> 32) as u32);
+    }
+
+    /// Write 0 to the status register and wait for the device to acknowledge the reset.
+    pub fn reset(&self) -> bool {
+        self.common_write8(COMMON_DEVICE_STATUS, 0);
+        let start = crate::timer::ticks();
+        while self.common_read8(COMMON_DEVICE_STATUS) != 0 {
+            if crate::timer::ticks().wrapping_sub(start) > 1000 {
+                return false;
+            }
+            core::hint::spin_loop();
+        }
+        true
+    }
+
+    pub fn status(&self) -> u8 {
+        self.common_read8(COMMON_DEVICE_STATUS)
+    }
+
+    pub fn add_status(&self, status: u8) {
+        let old = self.status();
+        self.common_write8(COMMON_DEVICE_STATUS, old | status);
+    }
+
+    pub fn device_features(&self) -> u64 {
+        self.common_write32(COMMON_DEVICE_FEATURE_SELECT, 0);
+        let low = self.common_read32(COMMON_DEVICE_FEATURE) as u64;
+        self.common_write32(COMMON_DEVICE_FEATURE_SELECT, 1);
+        let high = self.common_read32(COMMON_DEVICE_FEATURE) as u64;
+        (high << 32) | low
+    }
+
+    pub fn set_driver_features(&self, features: u64) {
+        self.common_write32(COMMON_DRIVER_FEATURE_SELECT, 0);
+        self.common_write32(COMMON_DRIVER_FEATURE, features as u32);
+        self.common_write32(COMMON_DRIVER_FEATURE_SELECT, 1);
+        self.common_write32(COMMON_DRIVER_FEATURE, (features >> 32) as u32);
+    }
+
+    pub fn num_queues(&self) -> u16 {
+        self.common_read16(COMMON_NUM_QUEUES)
+    }
+
+    pub fn queue_max_size(&self, queue: u16) -> u16 {
+        self.common_write16(COMMON_QUEUE_SELECT, queue);
+        self.common_read16(COMMON_QUEUE_SIZE)
+    }
+
+    /// Program and enable one virtqueue. Returns the address to write when notifying it.
+    pub fn setup_queue(&self, queue: u16, size: u16, desc: u64, driver: u64, device: u64) -> u64 {
+        self.common_write16(COMMON_QUEUE_SELECT, queue);
+        self.common_write16(COMMON_QUEUE_SIZE, size);
+        // Completions are polled; no MSI-X vector is routed.
+        self.common_write16(COMMON_QUEUE_MSIX_VECTOR, VIRTIO_MSI_NO_VECTOR);
+        self.common_write64(COMMON_QUEUE_DESC, desc);
+        self.common_write64(COMMON_QUEUE_DRIVER, driver);
+        self.common_write64(COMMON_QUEUE_DEVICE, device);
+        let notify_off = self.common_read16(COMMON_QUEUE_NOTIFY_OFF) as u64;
+        self.common_write16(COMMON_QUEUE_ENABLE, 1);
+        self.notify_base + notify_off * self.notify_multiplier as u64
+    }
+
+    pub fn notify(&self, notify_addr: u64, queue: u16) {
+        unsafe { write_volatile(notify_addr as *mut u16, queue) }
+    }
+
+    /// Reading the ISR status acknowledges it.
+    pub fn read_isr(&self) -> u8 {
+        unsafe { read_volatile(self.isr as *const u8) }
+    }
+
+    pub fn has_device_config(&self) -> bool {
+        self.device_cfg != 0
+    }
+
+    pub fn device_write8(&self, offset: u64, value: u8) {
+        if self.device_cfg == 0 {
+            return;
+        }
+        unsafe { write_volatile((self.device_cfg + offset) as *mut u8, value) }
+    }
+
+    pub fn device_read8(&self, offset: u64) -> u8 {
+        if self.device_cfg == 0 {
+            return 0;
+        }
+        unsafe { read_volatile((self.device_cfg + offset) as *const u8) }
+    }
+
+    pub fn device_read16(&self, offset: u64) -> u16 {
+        if self.device_cfg == 0 {
+            return 0;
+        }
+        unsafe { read_volatile((self.device_cfg + offset) as *const u16) }
+    }
+
+    pub fn device_read32(&self, offset: u64) -> u32 {
+        if self.device_cfg == 0 {
+            return 0;
+        }
+        unsafe { read_volatile((self.device_cfg + offset) as *const u32) }
+    }
+
+    pub fn device_read64(&self, offset: u64) -> u64 {
+        let low = self.device_read32(offset) as u64;
+        let high = self.device_read32(offset + 4) as u64;
+        (high << 32) | low
+    }
+}
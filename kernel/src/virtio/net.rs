@@ -2,11 +2,47 @@ use crate::pci::PciDevice;
 use crate::virtio::{VirtioDevice, VIRTIO_STATUS_ACKNOWLEDGE, VIRTIO_STATUS_DRIVER, VIRTIO_STATUS_DRIVER_OK, VIRTIO_STATUS_FAILED};
 use crate::virtio::queue::VirtQueue;
 use crate::println;
+use crate::spinlock::SpinLock;
+use alloc::boxed::Box;
+use alloc::string::String;
 use alloc::vec::Vec;
 use core::cell::RefCell;
 
-// VirtIO Net Header
+// Feature bits (virtio spec 5.1.3)
+const VIRTIO_NET_F_CSUM: u32 = 1 << 0;
+const VIRTIO_NET_F_GUEST_CSUM: u32 = 1 << 1;
+const VIRTIO_NET_F_MTU: u32 = 1 << 3;
+const VIRTIO_NET_F_MAC: u32 = 1 << 5;
+const VIRTIO_NET_F_MRG_RXBUF: u32 = 1 << 15;
+const VIRTIO_NET_F_STATUS: u32 = 1 << 16;
+
+// Header flags
+const VIRTIO_NET_HDR_F_NEEDS_CSUM: u8 = 1;
+const VIRTIO_NET_HDR_F_DATA_VALID: u8 = 2;
+
+// Device config: mac[6] then status (u16)
+const VIRTIO_NET_CFG_STATUS: u16 = 6;
+const VIRTIO_NET_S_LINK_UP: u16 = 1;
+// ...then max_virtqueue_pairs (u16) and mtu (u16) with VIRTIO_NET_F_MTU.
+const VIRTIO_NET_CFG_MTU: u16 = 10;
+
+const RX_BUFFER_SIZE: usize = 2048;
+const MTU_DEFAULT: usize = 1500;
+/// Largest MTU without a device limit; mergeable RX buffers carry it.
+const MTU_JUMBO: usize = 9000;
+/// Ethernet header and VLAN tag on top of the MTU.
+const FRAME_OVERHEAD: usize = 18;
+const QUEUE_SLOTS: usize = 32;
+const TX_MAX_SEGMENTS: usize = 8;
+const ETH_HEADER_LEN: usize = 14;
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+const IP_PROTO_TCP: u8 = 6;
+const IP_PROTO_UDP: u8 = 17;
+
+// VirtIO Net Header. `num_buffers` is only present when MRG_RXBUF is negotiated.
 #[repr(C, packed)]
+#[derive(Clone, Copy, Default)]
 struct VirtioNetHeader {
     flags: u8,
     gso_type: u8,
@@ -14,6 +50,20 @@ struct VirtioNetHeader {
     gso_size: u16,
     csum_start: u16,
     csum_offset: u16,
+    num_buffers: u16,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct VirtioNetStats {
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_merged: u64,
+    /// Merged frames whose continuation buffers never arrived.
+    pub rx_incomplete: u64,
+    pub rx_csum_completed: u64,
+    pub rx_csum_dropped: u64,
+    pub tx_offloaded: u64,
+    pub tx_ring_full: u64,
 }
 
 pub struct VirtioNetDriver {
@@ -21,174 +71,453 @@ pub struct VirtioNetDriver {
     rx_queue: VirtQueue,
     tx_queue: VirtQueue,
     mac: [u8; 6],
-    // Buffers for RX. 
-    // In a real driver, we'd have a pool of pages.
-    // Here we just keep track of buffers we gave to RX queue.
-    // For simplicity, we allocate new buffers when refilling? 
-    // Or we use a fixed set of static buffers?
-    // Let's use a slab of buffers.
-    rx_buffers: Vec<Vec<u8>>, 
+    features: u32,
+    header_len: usize,
+    rx_buffer_size: usize,
+    mtu: usize,
+    max_mtu: usize,
+    // RX buffers owned by the device, indexed by the head descriptor returned by add_buf.
+    rx_slots: Vec<Option<Vec<u8>>>,
+    // TX header + frame kept alive until the device hands the chain back.
+    tx_slots: Vec<Option<(Box<VirtioNetHeader>, Vec<Vec<u8>>)>>,
+    stats: VirtioNetStats,
 }
 
+// The queues point into DMA memory owned by the device; `GLOBAL_NET`
+// serializes every access to them.
+unsafe impl Send for VirtioNetDriver {}
+
 impl VirtioNetDriver {
     pub fn new(pci_dev: PciDevice) -> Option<Self> {
         let dev = VirtioDevice::new(pci_dev)?;
-        
+
         dev.reset();
         dev.add_status(VIRTIO_STATUS_ACKNOWLEDGE);
         dev.add_status(VIRTIO_STATUS_DRIVER);
-        
-        // Negotiate Features
-        // We want VIRTIO_NET_F_MAC (bit 5) usually.
-        // Bit 5 = 1 << 5 = 32
-        let features = dev.get_features();
-        if (features & (1 << 5)) != 0 {
-             dev.set_features(1 << 5);
-        } else {
-             dev.set_features(0);
-        }
 
-        let rx = VirtQueue::new(&dev, 0)?;
-        let tx = VirtQueue::new(&dev, 1)?;
-        
+        let offered = dev.get_features();
+        let features = offered
+            & (VIRTIO_NET_F_MAC
+                | VIRTIO_NET_F_CSUM
+                | VIRTIO_NET_F_GUEST_CSUM
+                | VIRTIO_NET_F_MTU
+                | VIRTIO_NET_F_MRG_RXBUF
+                | VIRTIO_NET_F_STATUS);
+        dev.set_features(features);
+
+        let Some(rx) = VirtQueue::new(&dev, 0) else {
+            dev.add_status(VIRTIO_STATUS_FAILED);
+            return None;
+        };
+        let Some(tx) = VirtQueue::new(&dev, 1) else {
+            dev.add_status(VIRTIO_STATUS_FAILED);
+            return None;
+        };
+
         dev.add_status(VIRTIO_STATUS_DRIVER_OK);
-        
+
         let mut mac = [0u8; 6];
         for i in 0..6 {
             mac[i] = dev.read_config_byte(i as u16);
         }
-        
+
+        // Legacy header is 10 bytes; MRG_RXBUF appends num_buffers.
+        let header_len = if (features & VIRTIO_NET_F_MRG_RXBUF) != 0 { 12 } else { 10 };
+        // The device may announce its MTU; without mergeable buffers every
+        // frame has to fit a single RX buffer.
+        let device_mtu = if (features & VIRTIO_NET_F_MTU) != 0 {
+            let lo = dev.read_config_byte(VIRTIO_NET_CFG_MTU) as usize;
+            let hi = dev.read_config_byte(VIRTIO_NET_CFG_MTU + 1) as usize;
+            Some((hi << 8) | lo)
+        } else {
+            None
+        };
+        let max_mtu = match device_mtu {
+            Some(mtu) => mtu.clamp(crate::net::mtu::MTU_MIN, MTU_JUMBO),
+            None if (features & VIRTIO_NET_F_MRG_RXBUF) != 0 => MTU_JUMBO,
+            None => MTU_DEFAULT,
+        };
+        let rx_buffer_size = if (features & VIRTIO_NET_F_MRG_RXBUF) != 0 {
+            RX_BUFFER_SIZE
+        } else {
+            (max_mtu + FRAME_OVERHEAD + header_len).max(RX_BUFFER_SIZE)
+        };
+        let mut rx_slots = Vec::with_capacity(QUEUE_SLOTS);
+        rx_slots.resize_with(QUEUE_SLOTS, || None);
+        let mut tx_slots = Vec::with_capacity(QUEUE_SLOTS);
+        tx_slots.resize_with(QUEUE_SLOTS, || None);
+
         let mut drv = Self {
             dev,
             rx_queue: rx,
             tx_queue: tx,
             mac,
-            rx_buffers: Vec::new(),
+            features,
+            header_len,
+            rx_buffer_size,
+            mtu: MTU_DEFAULT.min(max_mtu),
+            max_mtu,
+            rx_slots,
+            tx_slots,
+            stats: VirtioNetStats::default(),
         };
 
         // Fill RX queue
         drv.refill_rx();
-        
+
         Some(drv)
     }
 
     fn refill_rx(&mut self) {
-        // Populate RX queue with buffers
-        // We use 1500 byte buffers + header
-        // Header is 10 bytes (legacy) or 12 (modern)? 
-        // Legacy header is 10 bytes.
-        // We need 2 descriptors: Header (10) + Packet (1514)
-        // Or 1 descriptor if we merge them?
-        // Let's use 1 descriptor of size 1524?
-        // But the device writes header fields...
-        // Let's safe side: 2048 bytes buffer, use single descriptor?
-        // Device writes header at start of buffer.
-        
+        // One descriptor per buffer: the device writes the header at the start.
+        let mut added = false;
         while self.rx_queue.available_space() > 0 {
-            // Alloc a buffer
-            let mut vec = Vec::with_capacity(2048);
-            unsafe { vec.set_len(2048); }
-            
-            // Add to queue
-            // We pass slice.
-            unsafe {
-                if let Some(_idx) = self.rx_queue.add_buf(None, &vec, true) { // Write = true (Device writes)
-                     self.rx_buffers.push(vec);
-                } else {
-                    break;
+            let vec = alloc::vec![0u8; self.rx_buffer_size];
+            let head = unsafe { self.rx_queue.add_buf(None, &vec, true) };
+            match head {
+                Some(head) if (head as usize) < self.rx_slots.len() => {
+                    self.rx_slots[head as usize] = Some(vec);
+                    added = true;
                 }
+                _ => break,
             }
         }
-        self.rx_queue.notify(&self.dev);
+        if added {
+            self.rx_queue.notify(&self.dev);
+        }
+    }
+
+    pub fn tx_checksum_offload(&self) -> bool {
+        (self.features & VIRTIO_NET_F_CSUM) != 0
+    }
+
+    pub fn rx_checksum_offload(&self) -> bool {
+        (self.features & VIRTIO_NET_F_GUEST_CSUM) != 0
+    }
+
+    pub fn is_link_up(&self) -> bool {
+        if (self.features & VIRTIO_NET_F_STATUS) == 0 {
+            // Without the STATUS feature the link is always considered up.
+            return true;
+        }
+        let lo = self.dev.read_config_byte(VIRTIO_NET_CFG_STATUS) as u16;
+        let hi = self.dev.read_config_byte(VIRTIO_NET_CFG_STATUS + 1) as u16;
+        ((hi << 8) | lo) & VIRTIO_NET_S_LINK_UP != 0
+    }
+
+    pub fn mtu(&self) -> usize {
+        self.mtu
+    }
+
+    pub fn max_mtu(&self) -> usize {
+        self.max_mtu
+    }
+
+    /// Nothing to program: the device takes any frame up to its limit.
+    pub fn set_mtu(&mut self, mtu: usize) -> Result<(), &'static str> {
+        if !(crate::net::mtu::MTU_MIN..=self.max_mtu).contains(&mtu) {
+            return Err("MTU fuera de rango");
+        }
+        self.mtu = mtu;
+        Ok(())
+    }
+
+    pub fn stats(&self) -> VirtioNetStats {
+        self.stats
     }
 
     pub fn transmit(&mut self, packet: &[u8]) {
-        // virtio-net requires a header before the packet.
-        // In legacy, we just zero it?
-        // We need 1 buffer for header, 1 for packet.
-        let header = VirtioNetHeader {
-            flags: 0,
-            gso_type: 0,
-            hdr_len: 0,
-            gso_size: 0,
-            csum_start: 0,
-            csum_offset: 0,
+        self.transmit_segments(&[packet]);
+    }
+
+    /// Queue one frame built from several segments as a single descriptor chain:
+    /// [virtio header][segment 0]...[segment n]. With checksum offload the L3/L4
+    /// headers must sit in the first segment.
+    pub fn transmit_segments(&mut self, segments: &[&[u8]]) -> bool {
+        self.process_tx_cleanup();
+        if segments.is_empty() {
+            return false;
+        }
+
+        // Coalesce overly fragmented frames so the chain fits the 32-entry ring.
+        let mut owned: Vec<Vec<u8>> = if segments.len() > TX_MAX_SEGMENTS {
+            alloc::vec![segments.concat()]
+        } else {
+            segments.iter().map(|seg| seg.to_vec()).collect()
         };
-        
+        let frame_len: usize = owned.iter().map(|seg| seg.len()).sum();
+
+        let mut header = Box::new(VirtioNetHeader::default());
+        if self.tx_checksum_offload() {
+            if let Some((start, offset)) = prepare_tx_checksum_offload(&mut owned[0], frame_len) {
+                header.flags = VIRTIO_NET_HDR_F_NEEDS_CSUM;
+                header.csum_start = start as u16;
+                header.csum_offset = offset as u16;
+                self.stats.tx_offloaded = self.stats.tx_offloaded.saturating_add(1);
+            }
+        }
+
         let header_slice = unsafe {
-            core::slice::from_raw_parts(&header as *const _ as *const u8, core::mem::size_of::<VirtioNetHeader>())
+            core::slice::from_raw_parts(&*header as *const VirtioNetHeader as *const u8, self.header_len)
         };
-
-        unsafe {
-             self.tx_queue.add_buf(Some(header_slice), packet, false); // Write = false (Device reads)
+        let mut chain: Vec<(&[u8], bool)> = Vec::with_capacity(owned.len() + 1);
+        chain.push((header_slice, false));
+        for seg in owned.iter() {
+            chain.push((seg.as_slice(), false));
+        }
+        let head = unsafe { self.tx_queue.add_chain(&chain) };
+        drop(chain);
+        let Some(head) = head else {
+            self.stats.tx_ring_full = self.stats.tx_ring_full.saturating_add(1);
+            return false;
+        };
+        if (head as usize) < self.tx_slots.len() {
+            self.tx_slots[head as usize] = Some((header, owned));
         }
         self.tx_queue.notify(&self.dev);
-        
-        // Reclaim TX buffers?
-        // We assume synchronous or fire-and-forget for now?
-        // Eventually we need to pop used TX buffers to free descriptors.
-        self.process_tx_cleanup();
+        self.stats.tx_packets = self.stats.tx_packets.saturating_add(1);
+        true
     }
-    
+
     pub fn process_tx_cleanup(&mut self) {
         unsafe {
-            while let Some(_) = self.tx_queue.pop_used() {
-                // Just recycling descriptors currently
+            while let Some((id, _len)) = self.tx_queue.pop_used() {
+                if let Some(slot) = self.tx_slots.get_mut(id as usize) {
+                    *slot = None;
+                }
             }
         }
     }
 
+    fn take_rx_buffer(&mut self) -> Option<(Vec<u8>, usize)> {
+        let (id, len) = unsafe { self.rx_queue.pop_used()? };
+        let buf = self.rx_slots.get_mut(id as usize).and_then(|s| s.take())?;
+        Some((buf, (len as usize).min(self.rx_buffer_size)))
+    }
+
     pub fn receive(&mut self) -> Option<Vec<u8>> {
-        unsafe {
-            if let Some((_desc_id, len)) = self.rx_queue.pop_used() {
-                // We got a packet!
-                // Problem: We need to know WHICH buffer it was to return it.
-                // Our simple VirtQueue implementation recycles descriptors internally 
-                // but doesn't give us back the "cookie" or index easily to map to rx_buffers.
-                // This is a limitation of the simple queue.
-                
-                // For this prototype, we just treat rx_buffers as a FIFO matching the specific order?
-                // Real VirtIO drivers use 'id' field in descriptor as index into a buffer array.
-                // Our 'add_buf' returns 'head' index. And 'pop_used' returns 'id'.
-                // So if we track (head_id -> buffer_index), we can find it.
-                
-                // HACK: Since we assume FIFO for now (simplicity):
-                if !self.rx_buffers.is_empty() {
-                    let buf = self.rx_buffers.remove(0);
-                    // Actual length include header (10 bytes)
-                    // The payload starts at offset 10.
-                    let header_len = core::mem::size_of::<VirtioNetHeader>();
-                    if len as usize > header_len {
-                         let payload_len = len as usize - header_len;
-                         let mut packet = Vec::with_capacity(payload_len);
-                         packet.extend_from_slice(&buf[header_len..header_len+payload_len]);
-                         
-                         // Refill
-                         self.refill_rx();
-                         return Some(packet);
+        loop {
+            let (first, len) = self.take_rx_buffer()?;
+            if len <= self.header_len {
+                self.refill_rx();
+                continue;
+            }
+
+            let mut header = VirtioNetHeader::default();
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    first.as_ptr(),
+                    &mut header as *mut VirtioNetHeader as *mut u8,
+                    self.header_len,
+                );
+            }
+
+            let mut packet = Vec::with_capacity(len - self.header_len);
+            packet.extend_from_slice(&first[self.header_len..len]);
+
+            // Mergeable buffers: the rest of the frame follows in num_buffers - 1 buffers.
+            let num_buffers = if self.header_len == 12 { header.num_buffers.max(1) } else { 1 };
+            let mut complete = true;
+            for _ in 1..num_buffers {
+                match self.take_rx_buffer() {
+                    Some((buf, len)) => packet.extend_from_slice(&buf[..len]),
+                    None => {
+                        complete = false;
+                        break;
                     }
                 }
-                
-                self.refill_rx();
             }
+            if num_buffers > 1 {
+                self.stats.rx_merged = self.stats.rx_merged.saturating_add(1);
+            }
+            self.refill_rx();
+            if !complete {
+                self.stats.rx_incomplete = self.stats.rx_incomplete.saturating_add(1);
+                continue;
+            }
+
+            if self.rx_checksum_offload() {
+                if (header.flags & VIRTIO_NET_HDR_F_NEEDS_CSUM) != 0 {
+                    // Partial checksum from a host-local sender: finish it here.
+                    let start = header.csum_start as usize;
+                    let offset = header.csum_offset as usize;
+                    if start + offset + 2 > packet.len() {
+                        self.stats.rx_csum_dropped = self.stats.rx_csum_dropped.saturating_add(1);
+                        continue;
+                    }
+                    // The field already holds the pseudo-header sum, so summing the
+                    // segment as-is and complementing yields the final checksum.
+                    let csum = !fold_checksum(checksum_add(0, &packet[start..]));
+                    packet[start + offset..start + offset + 2].copy_from_slice(&csum.to_be_bytes());
+                    self.stats.rx_csum_completed = self.stats.rx_csum_completed.saturating_add(1);
+                } else if (header.flags & VIRTIO_NET_HDR_F_DATA_VALID) == 0 && !verify_l4_checksum(&packet) {
+                    // smoltcp skips verification when GUEST_CSUM is on, so check unvalidated frames here.
+                    self.stats.rx_csum_dropped = self.stats.rx_csum_dropped.saturating_add(1);
+                    continue;
+                }
+            }
+
+            self.stats.rx_packets = self.stats.rx_packets.saturating_add(1);
+            return Some(packet);
         }
-        None
     }
-    
+
     pub fn mac_address(&self) -> [u8; 6] {
         self.mac
     }
 }
 
+fn checksum_add(mut sum: u32, data: &[u8]) -> u32 {
+    let mut chunks = data.chunks_exact(2);
+    for pair in &mut chunks {
+        sum = sum.wrapping_add(u16::from_be_bytes([pair[0], pair[1]]) as u32);
+    }
+    if let [last] = chunks.remainder() {
+        sum = sum.wrapping_add((*last as u32) << 8);
+    }
+    sum
+}
+
+fn fold_checksum(mut sum: u32) -> u16 {
+    while (sum >> 16) != 0 {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    sum as u16
+}
+
+/// Locate the L4 header of an Ethernet frame: (l4_start, protocol, pseudo-header sum).
+/// `head` must contain the Ethernet and IP headers; `frame_len` is the whole frame.
+fn l4_pseudo_header(head: &[u8], frame_len: usize) -> Option<(usize, u8, u32)> {
+    if head.len() < ETH_HEADER_LEN || frame_len < head.len() {
+        return None;
+    }
+    let ethertype = u16::from_be_bytes([head[12], head[13]]);
+    let ip = &head[ETH_HEADER_LEN..];
+    let ip_len = frame_len - ETH_HEADER_LEN;
+    match ethertype {
+        ETHERTYPE_IPV4 => {
+            if ip.len() < 20 {
+                return None;
+            }
+            let ihl = ((ip[0] & 0x0F) as usize) * 4;
+            let total_len = u16::from_be_bytes([ip[2], ip[3]]) as usize;
+            let fragmented = (u16::from_be_bytes([ip[6], ip[7]]) & 0x3FFF) != 0;
+            if ihl < 20 || ihl > ip.len() || total_len < ihl || total_len > ip_len || fragmented {
+                return None;
+            }
+            let proto = ip[9];
+            let l4_len = (total_len - ihl) as u32;
+            let sum = checksum_add(0, &ip[12..20]) + proto as u32 + l4_len;
+            Some((ETH_HEADER_LEN + ihl, proto, sum))
+        }
+        ETHERTYPE_IPV6 => {
+            if ip.len() < 40 {
+                return None;
+            }
+            // Extension headers are not offloaded.
+            let proto = ip[6];
+            let l4_len = u16::from_be_bytes([ip[4], ip[5]]) as u32;
+            if 40 + l4_len as usize > ip_len {
+                return None;
+            }
+            let sum = checksum_add(0, &ip[8..40]) + proto as u32 + l4_len;
+            Some((ETH_HEADER_LEN + 40, proto, sum))
+        }
+        _ => None,
+    }
+}
+
+/// Seed the TCP/UDP checksum field with the pseudo-header sum so the device can
+/// finish it. Returns (csum_start, csum_offset) for the virtio header.
+fn prepare_tx_checksum_offload(frame: &mut [u8], frame_len: usize) -> Option<(usize, usize)> {
+    let (start, proto, pseudo) = l4_pseudo_header(frame, frame_len)?;
+    let offset = match proto {
+        IP_PROTO_TCP => 16,
+        IP_PROTO_UDP => 6,
+        _ => return None,
+    };
+    if start + offset + 2 > frame.len() {
+        return None;
+    }
+    let seed = fold_checksum(pseudo);
+    frame[start + offset..start + offset + 2].copy_from_slice(&seed.to_be_bytes());
+    Some((start, offset))
+}
+
+/// Software TCP/UDP checksum check for frames the device did not validate.
+fn verify_l4_checksum(frame: &[u8]) -> bool {
+    let Some((start, proto, pseudo)) = l4_pseudo_header(frame, frame.len()) else {
+        return true;
+    };
+    if proto != IP_PROTO_TCP && proto != IP_PROTO_UDP {
+        return true;
+    }
+    let ethertype = u16::from_be_bytes([frame[12], frame[13]]);
+    let l4_end = if ethertype == ETHERTYPE_IPV4 {
+        ETH_HEADER_LEN + u16::from_be_bytes([frame[16], frame[17]]) as usize
+    } else {
+        start + u16::from_be_bytes([frame[18], frame[19]]) as usize
+    };
+    let segment = &frame[start..l4_end.min(frame.len())];
+    if proto == IP_PROTO_UDP && ethertype == ETHERTYPE_IPV4 && segment.len() >= 8 && segment[6] == 0 && segment[7] == 0 {
+        return true;
+    }
+    fold_checksum(checksum_add(pseudo, segment)) == 0xFFFF
+}
+
 // Global Network Driver
-pub static mut GLOBAL_NET: Option<VirtioNetDriver> = None;
+pub static GLOBAL_NET: SpinLock<Option<VirtioNetDriver>> = SpinLock::new(None);
+
+pub fn is_present() -> bool {
+    GLOBAL_NET.lock().is_some()
+}
+
+pub fn is_link_up() -> bool {
+    GLOBAL_NET.lock().as_ref().map(|d| d.is_link_up()).unwrap_or(false)
+}
+
+pub fn mac_address() -> Option<[u8; 6]> {
+    GLOBAL_NET.lock().as_ref().map(|d| d.mac_address())
+}
+
+pub fn status_line() -> String {
+    let guard = GLOBAL_NET.lock();
+    match guard.as_ref() {
+        Some(drv) => {
+            let st = drv.stats();
+            alloc::format!(
+                "VirtIO Net: enlace={} csum tx={} rx={} mrg_rxbuf={} rx={} tx={} tx_offload={} rx_merged={} csum_fix={} csum_drop={} tx_full={}",
+                if drv.is_link_up() { "up" } else { "down" },
+                if drv.tx_checksum_offload() { "si" } else { "no" },
+                if drv.rx_checksum_offload() { "si" } else { "no" },
+                if drv.header_len == 12 { "si" } else { "no" },
+                st.rx_packets,
+                st.tx_packets,
+                st.tx_offloaded,
+                st.rx_merged,
+                st.rx_csum_completed,
+                st.rx_csum_dropped,
+                st.tx_ring_full
+            )
+        }
+        None => String::from("VirtIO Net: sin dispositivo."),
+    }
+}
 
 pub fn init(pci_dev: PciDevice) {
     if let Some(drv) = VirtioNetDriver::new(pci_dev) {
-        crate::println(&alloc::format!("VirtIO Net: Initialized. MAC: {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}", 
+        crate::println(&alloc::format!("VirtIO Net: Initialized. MAC: {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
             drv.mac[0], drv.mac[1], drv.mac[2], drv.mac[3], drv.mac[4], drv.mac[5]));
-            
-        unsafe { GLOBAL_NET = Some(drv); }
+
+        let node = crate::device::add_class_device(
+            Some(crate::device::pci_function(&pci_dev)), "eth", "net", "virtio-net");
+        crate::device::set_attr(node, "address", crate::net::stats::mac_text(&drv.mac).as_str());
+        crate::device::set_live(node, "operstate", || String::from(if is_link_up() { "up" } else { "down" }));
+        crate::device::set_live(node, "mtu", || {
+            alloc::format!("{}", GLOBAL_NET.lock().as_ref().map(|d| d.mtu()).unwrap_or(0))
+        });
+
+        *GLOBAL_NET.lock() = Some(drv);
     } else {
         println("VirtIO Net: Failed to initialize.");
     }
@@ -0,0 +1,661 @@
+//! virtio-9p transport and 9P2000.L client.
+//!
+//! QEMU exports a host directory with
+//! `-fsdev local,id=fs0,path=<dir>,security_model=none -device virtio-9p-pci,fsdev=fs0,mount_tag=host`.
+//! Requests are issued synchronously over the single request queue: one
+//! device-readable T-message buffer followed by one device-writable R-message
+//! buffer. `crate::hostfs` maps the export to `/host`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{fence, Ordering};
+
+use crate::memory;
+use crate::pci::PciDevice;
+use crate::println;
+use crate::sync::KernelCell;
+use crate::virtio::modern::{ModernTransport, VIRTIO_F_VERSION_1};
+use crate::virtio::queue::{VirtqDesc, VRING_DESC_F_NEXT, VRING_DESC_F_WRITE};
+use crate::virtio::{
+    VIRTIO_STATUS_ACKNOWLEDGE, VIRTIO_STATUS_DRIVER, VIRTIO_STATUS_DRIVER_OK,
+    VIRTIO_STATUS_FAILED, VIRTIO_STATUS_FEATURES_OK,
+};
+
+const VIRTIO_9P_MOUNT_TAG: u64 = 1 << 0;
+
+const REQUEST_QUEUE: u16 = 0;
+const P9_MSIZE: u32 = 64 * 1024;
+const P9_IO_HEADER: u32 = 24;
+const P9_TIMEOUT_TICKS: u64 = 5_000;
+const P9_NOTAG: u16 = 0xFFFF;
+const P9_NOFID: u32 = 0xFFFF_FFFF;
+const P9_VERSION: &str = "9P2000.L";
+
+// 9P2000.L message types.
+const RLERROR: u8 = 7;
+const TLOPEN: u8 = 12;
+const TLCREATE: u8 = 14;
+const TGETATTR: u8 = 24;
+const TREADDIR: u8 = 40;
+const TMKDIR: u8 = 72;
+const TUNLINKAT: u8 = 76;
+const TVERSION: u8 = 100;
+const TATTACH: u8 = 104;
+const TWALK: u8 = 110;
+const TREAD: u8 = 116;
+const TWRITE: u8 = 118;
+const TCLUNK: u8 = 120;
+
+const P9_GETATTR_BASIC: u64 = 0x0000_07FF;
+const P9_QTDIR: u8 = 0x80;
+const P9_MAXWELEM: usize = 16;
+const AT_REMOVEDIR: u32 = 0x200;
+
+pub const O_RDONLY: u32 = 0;
+pub const O_WRONLY: u32 = 1;
+pub const O_CREAT: u32 = 0o100;
+pub const O_TRUNC: u32 = 0o1000;
+
+#[derive(Clone, Copy, Default)]
+pub struct Qid {
+    pub kind: u8,
+    pub version: u32,
+    pub path: u64,
+}
+
+impl Qid {
+    pub fn is_dir(&self) -> bool {
+        self.kind & P9_QTDIR != 0
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct P9Attr {
+    pub qid: Qid,
+    pub mode: u32,
+    pub size: u64,
+    pub mtime_sec: u64,
+}
+
+#[derive(Clone)]
+pub struct P9DirEntry {
+    pub name: String,
+    pub qid: Qid,
+}
+
+/// What a walk to a missing name fails with.
+pub const NOT_FOUND: &str = "9P: no such file or directory";
+
+fn errno_str(errno: u32) -> &'static str {
+    match errno {
+        1 => "9P: operation not permitted",
+        2 => NOT_FOUND,
+        5 => "9P: I/O error",
+        13 => "9P: permission denied",
+        17 => "9P: file exists",
+        20 => "9P: not a directory",
+        21 => "9P: is a directory",
+        22 => "9P: invalid argument",
+        28 => "9P: no space left on host",
+        30 => "9P: read-only export",
+        36 => "9P: name too long",
+        39 => "9P: directory not empty",
+        _ => "9P: host error",
+    }
+}
+
+struct P9Writer {
+    buf: Vec<u8>,
+}
+
+impl P9Writer {
+    fn new(kind: u8, tag: u16) -> Self {
+        let mut buf = Vec::with_capacity(64);
+        buf.extend_from_slice(&[0, 0, 0, 0]);
+        buf.push(kind);
+        buf.extend_from_slice(&tag.to_le_bytes());
+        Self { buf }
+    }
+
+    fn u16(mut self, v: u16) -> Self {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    fn u32(mut self, v: u32) -> Self {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    fn u64(mut self, v: u64) -> Self {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    fn str(mut self, s: &str) -> Self {
+        let len = s.len().min(u16::MAX as usize);
+        self.buf.extend_from_slice(&(len as u16).to_le_bytes());
+        self.buf.extend_from_slice(&s.as_bytes()[..len]);
+        self
+    }
+
+    fn bytes(mut self, data: &[u8]) -> Self {
+        self.buf.extend_from_slice(data);
+        self
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let len = self.buf.len() as u32;
+        self.buf[0..4].copy_from_slice(&len.to_le_bytes());
+        self.buf
+    }
+}
+
+struct P9Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> P9Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], &'static str> {
+        if self.pos + n > self.data.len() {
+            return Err("9P: truncated reply");
+        }
+        let out = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8, &'static str> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, &'static str> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, &'static str> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, &'static str> {
+        let b = self.take(8)?;
+        let mut raw = [0u8; 8];
+        raw.copy_from_slice(b);
+        Ok(u64::from_le_bytes(raw))
+    }
+
+    fn str(&mut self) -> Result<String, &'static str> {
+        let len = self.u16()? as usize;
+        let raw = self.take(len)?;
+        Ok(String::from_utf8_lossy(raw).into_owned())
+    }
+
+    fn qid(&mut self) -> Result<Qid, &'static str> {
+        Ok(Qid { kind: self.u8()?, version: self.u32()?, path: self.u64()? })
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+}
+
+/// Two-descriptor synchronous request queue.
+struct RequestQueue {
+    size: u16,
+    desc: *mut VirtqDesc,
+    avail: u64,
+    used: u64,
+    notify_addr: u64,
+    avail_idx: u16,
+    last_used: u16,
+    tx: Vec<u8>,
+    rx: Vec<u8>,
+}
+
+impl RequestQueue {
+    fn new(transport: &ModernTransport, size: u16, msize: u32) -> Option<Self> {
+        let ring_frame = memory::alloc_frame()?;
+        let used_frame = memory::alloc_frame()?;
+        unsafe {
+            core::ptr::write_bytes(ring_frame as *mut u8, 0, 4096);
+            core::ptr::write_bytes(used_frame as *mut u8, 0, 4096);
+        }
+        let avail = ring_frame + size as u64 * 16;
+        let notify_addr = transport.setup_queue(REQUEST_QUEUE, size, ring_frame, avail, used_frame);
+        Some(Self {
+            size,
+            desc: ring_frame as *mut VirtqDesc,
+            avail,
+            used: used_frame,
+            notify_addr,
+            avail_idx: 0,
+            last_used: 0,
+            tx: alloc::vec![0u8; msize as usize],
+            rx: alloc::vec![0u8; msize as usize],
+        })
+    }
+
+    /// Send `msg` and wait for the reply. Returns the reply length in `self.rx`.
+    fn transact(&mut self, transport: &ModernTransport, msg: &[u8]) -> Result<usize, &'static str> {
+        if msg.len() > self.tx.len() {
+            return Err("9P: request larger than msize");
+        }
+        self.tx[..msg.len()].copy_from_slice(msg);
+        unsafe {
+            core::ptr::write_volatile(
+                self.desc,
+                VirtqDesc {
+                    addr: self.tx.as_ptr() as u64,
+                    len: msg.len() as u32,
+                    flags: VRING_DESC_F_NEXT,
+                    next: 1,
+                },
+            );
+            core::ptr::write_volatile(
+                self.desc.add(1),
+                VirtqDesc {
+                    addr: self.rx.as_mut_ptr() as u64,
+                    len: self.rx.len() as u32,
+                    flags: VRING_DESC_F_WRITE,
+                    next: 0,
+                },
+            );
+            let ring_entry = self.avail + 4 + 2 * (self.avail_idx % self.size) as u64;
+            core::ptr::write_volatile(ring_entry as *mut u16, 0);
+            fence(Ordering::SeqCst);
+            self.avail_idx = self.avail_idx.wrapping_add(1);
+            core::ptr::write_volatile((self.avail + 2) as *mut u16, self.avail_idx);
+            fence(Ordering::SeqCst);
+        }
+        transport.notify(self.notify_addr, REQUEST_QUEUE);
+
+        let start = crate::timer::ticks();
+        loop {
+            let used_idx = unsafe { core::ptr::read_volatile((self.used + 2) as *const u16) };
+            if used_idx != self.last_used {
+                break;
+            }
+            if crate::timer::ticks().wrapping_sub(start) > P9_TIMEOUT_TICKS {
+                return Err("9P: host did not answer");
+            }
+            core::hint::spin_loop();
+        }
+        fence(Ordering::SeqCst);
+        let elem = self.used + 4 + 8 * (self.last_used % self.size) as u64;
+        let len = unsafe { core::ptr::read_volatile((elem + 4) as *const u32) } as usize;
+        self.last_used = self.last_used.wrapping_add(1);
+        Ok(len.min(self.rx.len()))
+    }
+}
+
+pub struct P9Client {
+    transport: ModernTransport,
+    queue: RequestQueue,
+    mount_tag: String,
+    msize: u32,
+    root_fid: u32,
+    next_fid: u32,
+    free_fids: Vec<u32>,
+    next_tag: u16,
+    requests: u64,
+    errors: u64,
+}
+
+/// The `/host` export. RPCs spin on the timer while they wait for the
+/// device, so this is kernel-thread state rather than a `SpinLock`.
+static P9_MOUNT: KernelCell<Option<P9Client>> = KernelCell::new(None);
+
+impl P9Client {
+    fn rpc(&mut self, msg: Vec<u8>, expect: u8) -> Result<Vec<u8>, &'static str> {
+        self.requests = self.requests.saturating_add(1);
+        let len = match self.queue.transact(&self.transport, msg.as_slice()) {
+            Ok(len) => len,
+            Err(e) => {
+                self.errors = self.errors.saturating_add(1);
+                return Err(e);
+            }
+        };
+        let reply = &self.queue.rx[..len];
+        let mut r = P9Reader::new(reply);
+        let size = r.u32()? as usize;
+        let kind = r.u8()?;
+        let _tag = r.u16()?;
+        if size > len {
+            self.errors = self.errors.saturating_add(1);
+            return Err("9P: truncated reply");
+        }
+        if kind == RLERROR {
+            self.errors = self.errors.saturating_add(1);
+            return Err(errno_str(r.u32()?));
+        }
+        if kind != expect {
+            self.errors = self.errors.saturating_add(1);
+            return Err("9P: unexpected reply");
+        }
+        Ok(reply[7..size].to_vec())
+    }
+
+    fn tag(&mut self) -> u16 {
+        self.next_tag = self.next_tag.wrapping_add(1);
+        if self.next_tag == P9_NOTAG {
+            self.next_tag = 1;
+        }
+        self.next_tag
+    }
+
+    fn alloc_fid(&mut self) -> u32 {
+        if let Some(fid) = self.free_fids.pop() {
+            return fid;
+        }
+        let fid = self.next_fid;
+        self.next_fid = self.next_fid.wrapping_add(1);
+        fid
+    }
+
+    fn handshake(&mut self) -> Result<(), &'static str> {
+        let msg = P9Writer::new(TVERSION, P9_NOTAG).u32(self.msize).str(P9_VERSION).finish();
+        let reply = self.rpc(msg, TVERSION + 1)?;
+        let mut r = P9Reader::new(&reply);
+        let msize = r.u32()?;
+        if r.str()? != P9_VERSION {
+            return Err("9P: host does not speak 9P2000.L");
+        }
+        self.msize = msize.min(self.msize);
+
+        let fid = self.alloc_fid();
+        let tag = self.tag();
+        let msg = P9Writer::new(TATTACH, tag)
+            .u32(fid)
+            .u32(P9_NOFID)
+            .str("root")
+            .str("")
+            .u32(0)
+            .finish();
+        self.rpc(msg, TATTACH + 1)?;
+        self.root_fid = fid;
+        Ok(())
+    }
+
+    /// Walk from the export root to `path`, returning a fresh fid.
+    pub fn walk(&mut self, path: &[&str]) -> Result<u32, &'static str> {
+        let fid = self.alloc_fid();
+        let mut from = self.root_fid;
+        let mut rest = path;
+        loop {
+            let chunk = &rest[..rest.len().min(P9_MAXWELEM)];
+            let tag = self.tag();
+            let mut msg = P9Writer::new(TWALK, tag).u32(from).u32(fid).u16(chunk.len() as u16);
+            for name in chunk.iter() {
+                msg = msg.str(name);
+            }
+            let reply = match self.rpc(msg.finish(), TWALK + 1) {
+                Ok(reply) => reply,
+                Err(e) => {
+                    if from == fid {
+                        self.clunk(fid);
+                    } else {
+                        self.free_fids.push(fid);
+                    }
+                    return Err(e);
+                }
+            };
+            let walked = P9Reader::new(&reply).u16()? as usize;
+            if walked != chunk.len() {
+                // A partial walk does not bind newfid.
+                if from == fid {
+                    self.clunk(fid);
+                } else {
+                    self.free_fids.push(fid);
+                }
+                return Err(errno_str(2));
+            }
+            rest = &rest[chunk.len()..];
+            from = fid;
+            if rest.is_empty() {
+                return Ok(fid);
+            }
+        }
+    }
+
+    pub fn clunk(&mut self, fid: u32) {
+        let tag = self.tag();
+        let msg = P9Writer::new(TCLUNK, tag).u32(fid).finish();
+        let _ = self.rpc(msg, TCLUNK + 1);
+        // The fid is released even when the clunk itself fails.
+        self.free_fids.push(fid);
+    }
+
+    pub fn getattr(&mut self, fid: u32) -> Result<P9Attr, &'static str> {
+        let tag = self.tag();
+        let msg = P9Writer::new(TGETATTR, tag).u32(fid).u64(P9_GETATTR_BASIC).finish();
+        let reply = self.rpc(msg, TGETATTR + 1)?;
+        let mut r = P9Reader::new(&reply);
+        let _valid = r.u64()?;
+        let qid = r.qid()?;
+        let mode = r.u32()?;
+        let _uid = r.u32()?;
+        let _gid = r.u32()?;
+        let _nlink = r.u64()?;
+        let _rdev = r.u64()?;
+        let size = r.u64()?;
+        let _blksize = r.u64()?;
+        let _blocks = r.u64()?;
+        let _atime = (r.u64()?, r.u64()?);
+        let mtime_sec = r.u64()?;
+        Ok(P9Attr { qid, mode, size, mtime_sec })
+    }
+
+    pub fn lopen(&mut self, fid: u32, flags: u32) -> Result<Qid, &'static str> {
+        let tag = self.tag();
+        let msg = P9Writer::new(TLOPEN, tag).u32(fid).u32(flags).finish();
+        let reply = self.rpc(msg, TLOPEN + 1)?;
+        P9Reader::new(&reply).qid()
+    }
+
+    /// Create `name` inside the directory `dir_fid`; on success `dir_fid` refers to the new open file.
+    pub fn lcreate(&mut self, dir_fid: u32, name: &str, flags: u32, mode: u32) -> Result<Qid, &'static str> {
+        let tag = self.tag();
+        let msg = P9Writer::new(TLCREATE, tag)
+            .u32(dir_fid)
+            .str(name)
+            .u32(flags)
+            .u32(mode)
+            .u32(0)
+            .finish();
+        let reply = self.rpc(msg, TLCREATE + 1)?;
+        P9Reader::new(&reply).qid()
+    }
+
+    pub fn mkdir(&mut self, dir_fid: u32, name: &str, mode: u32) -> Result<Qid, &'static str> {
+        let tag = self.tag();
+        let msg = P9Writer::new(TMKDIR, tag).u32(dir_fid).str(name).u32(mode).u32(0).finish();
+        let reply = self.rpc(msg, TMKDIR + 1)?;
+        P9Reader::new(&reply).qid()
+    }
+
+    pub fn unlinkat(&mut self, dir_fid: u32, name: &str, is_dir: bool) -> Result<(), &'static str> {
+        let tag = self.tag();
+        let flags = if is_dir { AT_REMOVEDIR } else { 0 };
+        let msg = P9Writer::new(TUNLINKAT, tag).u32(dir_fid).str(name).u32(flags).finish();
+        self.rpc(msg, TUNLINKAT + 1).map(|_| ())
+    }
+
+    fn io_chunk(&self) -> u32 {
+        self.msize.saturating_sub(P9_IO_HEADER)
+    }
+
+    pub fn read(&mut self, fid: u32, offset: u64, count: u32) -> Result<Vec<u8>, &'static str> {
+        let tag = self.tag();
+        let count = count.min(self.io_chunk());
+        let msg = P9Writer::new(TREAD, tag).u32(fid).u64(offset).u32(count).finish();
+        let reply = self.rpc(msg, TREAD + 1)?;
+        let mut r = P9Reader::new(&reply);
+        let n = r.u32()? as usize;
+        Ok(r.take(n)?.to_vec())
+    }
+
+    pub fn write(&mut self, fid: u32, offset: u64, data: &[u8]) -> Result<usize, &'static str> {
+        let tag = self.tag();
+        let chunk = &data[..data.len().min(self.io_chunk() as usize)];
+        let msg = P9Writer::new(TWRITE, tag)
+            .u32(fid)
+            .u64(offset)
+            .u32(chunk.len() as u32)
+            .bytes(chunk)
+            .finish();
+        let reply = self.rpc(msg, TWRITE + 1)?;
+        Ok(P9Reader::new(&reply).u32()? as usize)
+    }
+
+    /// Read every entry of an opened directory fid.
+    pub fn readdir(&mut self, fid: u32) -> Result<Vec<P9DirEntry>, &'static str> {
+        let mut out = Vec::new();
+        let mut offset = 0u64;
+        loop {
+            let tag = self.tag();
+            let msg = P9Writer::new(TREADDIR, tag).u32(fid).u64(offset).u32(self.io_chunk()).finish();
+            let reply = self.rpc(msg, TREADDIR + 1)?;
+            let mut r = P9Reader::new(&reply);
+            let count = r.u32()? as usize;
+            if count == 0 {
+                return Ok(out);
+            }
+            let mut entries = P9Reader::new(r.take(count)?);
+            while entries.remaining() > 0 {
+                let qid = entries.qid()?;
+                offset = entries.u64()?;
+                let _kind = entries.u8()?;
+                let name = entries.str()?;
+                if name != "." && name != ".." {
+                    out.push(P9DirEntry { name, qid });
+                }
+            }
+        }
+    }
+
+    pub fn status_line(&self) -> String {
+        alloc::format!(
+            "9P: tag '{}' msize={} peticiones={} errores={}",
+            self.mount_tag,
+            self.msize,
+            self.requests,
+            self.errors
+        )
+    }
+}
+
+fn read_mount_tag(transport: &ModernTransport) -> String {
+    let len = transport.device_read16(0).min(255) as u64;
+    let mut tag = String::new();
+    for i in 0..len {
+        let byte = transport.device_read8(2 + i);
+        if byte == 0 {
+            break;
+        }
+        tag.push(byte as char);
+    }
+    tag
+}
+
+pub fn init(device: PciDevice) {
+    if is_mounted() {
+        println("VirtIO 9P: additional export ignored (only one /host mount).");
+        return;
+    }
+    let Some(transport) = ModernTransport::probe(device) else {
+        println("VirtIO 9P: missing virtio 1.0 capabilities.");
+        return;
+    };
+    if !transport.reset() {
+        println("VirtIO 9P: device reset failed.");
+        return;
+    }
+    transport.add_status(VIRTIO_STATUS_ACKNOWLEDGE);
+    transport.add_status(VIRTIO_STATUS_DRIVER);
+
+    let offered = transport.device_features();
+    if offered & VIRTIO_F_VERSION_1 == 0 {
+        transport.add_status(VIRTIO_STATUS_FAILED);
+        println("VirtIO 9P: device does not offer VERSION_1.");
+        return;
+    }
+    transport.set_driver_features(VIRTIO_F_VERSION_1 | (offered & VIRTIO_9P_MOUNT_TAG));
+    transport.add_status(VIRTIO_STATUS_FEATURES_OK);
+    if transport.status() & VIRTIO_STATUS_FEATURES_OK == 0 {
+        transport.add_status(VIRTIO_STATUS_FAILED);
+        println("VirtIO 9P: FEATURES_OK rejected.");
+        return;
+    }
+
+    let mount_tag = if offered & VIRTIO_9P_MOUNT_TAG != 0 {
+        read_mount_tag(&transport)
+    } else {
+        String::new()
+    };
+
+    let size = transport.queue_max_size(REQUEST_QUEUE).min(8);
+    if size < 2 {
+        transport.add_status(VIRTIO_STATUS_FAILED);
+        println("VirtIO 9P: request queue unavailable.");
+        return;
+    }
+    let Some(queue) = RequestQueue::new(&transport, size, P9_MSIZE) else {
+        transport.add_status(VIRTIO_STATUS_FAILED);
+        println("VirtIO 9P: out of memory for request queue.");
+        return;
+    };
+    transport.add_status(VIRTIO_STATUS_DRIVER_OK);
+
+    let mut client = P9Client {
+        transport,
+        queue,
+        mount_tag,
+        msize: P9_MSIZE,
+        root_fid: 0,
+        next_fid: 1,
+        free_fids: Vec::new(),
+        next_tag: 0,
+        requests: 0,
+        errors: 0,
+    };
+    match client.handshake() {
+        Ok(()) => {
+            println(&alloc::format!(
+                "VirtIO 9P: export '{}' montado en {}",
+                client.mount_tag,
+                crate::hostfs::HOST_MOUNT
+            ));
+            // SAFETY: probes run on the kernel thread and nothing holds the
+            // client before it is mounted.
+            unsafe {
+                *P9_MOUNT.get_mut() = Some(client);
+            }
+        }
+        Err(e) => {
+            client.transport.add_status(VIRTIO_STATUS_FAILED);
+            println(&alloc::format!("VirtIO 9P: attach failed: {}", e));
+        }
+    }
+}
+
+/// The mounted client, if any.
+///
+/// # Safety
+///
+/// Kernel thread only, and no earlier borrow from `client` may still be in
+/// use: callers take it once and finish with it before taking it again.
+pub unsafe fn client() -> Option<&'static mut P9Client> {
+    unsafe { P9_MOUNT.get_mut() }.as_mut()
+}
+
+pub fn is_mounted() -> bool {
+    // SAFETY: kernel thread; only reads whether the slot is filled.
+    unsafe { P9_MOUNT.get() }.is_some()
+}
@@ -171,6 +171,39 @@ impl VirtQueue {
         Some(head)
     }
     
+    // Add an arbitrary descriptor chain. Each entry is (buffer, device_writes).
+    // Returns the head descriptor index, or None if the ring lacks free slots.
+    pub unsafe fn add_chain(&mut self, buffers: &[(&[u8], bool)]) -> Option<u16> {
+        let needed = buffers.len() as u16;
+        if needed == 0 || self.num_free < needed {
+            return None;
+        }
+
+        let head = self.free_head;
+        let mut curr = head;
+        for (i, (buf, device_writes)) in buffers.iter().enumerate() {
+            let desc = self.desc.add(curr as usize);
+            (*desc).addr = buf.as_ptr() as u64;
+            (*desc).len = buf.len() as u32;
+            (*desc).flags = if *device_writes { VRING_DESC_F_WRITE } else { 0 };
+            // The free list links double as chain links.
+            if i + 1 < buffers.len() {
+                (*desc).flags |= VRING_DESC_F_NEXT;
+            }
+            curr = (*desc).next;
+        }
+
+        self.num_free -= needed;
+        self.free_head = curr;
+
+        let avail_idx = (*self.avail).idx;
+        (*self.avail).ring[avail_idx as usize % 32] = head;
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+        (*self.avail).idx = avail_idx.wrapping_add(1);
+
+        Some(head)
+    }
+
     // Check for used buffers
     // Returns (desc_index, length)
     pub unsafe fn pop_used(&mut self) -> Option<(u32, u32)> {
@@ -2,6 +2,9 @@ use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
 use core::sync::atomic::{AtomicBool, Ordering};
+use redux_netparse::gemini::{gemini_mime, parse_gemtext, GemLine};
+use redux_netparse::gopher::{gopher_text_body, parse_gopher_menu};
+use redux_netparse::url::resolve_reference;
 
 const MAX_REDIRECTS: usize = 6;
 const MAX_RENDER_LINES: usize = 480;
@@ -74,6 +77,8 @@ struct ParsedHttp {
     status_code: Option<u16>,
     headers: Vec<(String, String)>,
     body: String,
+    /// Raw body when the response is a PDF (`application/pdf` or `%PDF-`).
+    pdf: Option<Vec<u8>>,
 }
 
 pub struct BrowserRenderOutput {
@@ -245,6 +250,7 @@ fn parse_http_response(raw: &str) -> ParsedHttp {
             status_code: None,
             headers: Vec::new(),
             body: ascii,
+            pdf: None,
         };
     }
 
@@ -278,9 +284,32 @@ fn parse_http_response(raw: &str) -> ParsedHttp {
         status_code,
         headers,
         body: String::from(body),
+        pdf: None,
     }
 }
 
+/// `parse_http_response` on the raw bytes, keeping PDF bodies intact: the
+/// text body is a lossy ASCII copy that would corrupt them.
+fn parse_http_bytes(raw: Vec<u8>) -> ParsedHttp {
+    let mut parsed = parse_http_response(String::from_utf8_lossy(raw.as_slice()).as_ref());
+    let body_start = if parsed.status_line.is_none() {
+        0
+    } else if let Some(idx) = find_subslice(raw.as_slice(), b"\r\n\r\n", 0) {
+        idx + 4
+    } else if let Some(idx) = find_subslice(raw.as_slice(), b"\n\n", 0) {
+        idx + 2
+    } else {
+        raw.len()
+    };
+    let body = &raw[body_start.min(raw.len())..];
+    let typed_pdf = header_value(&parsed, "content-type").is_some_and(|t| t.contains("application/pdf"));
+    if typed_pdf || body.starts_with(b"%PDF-") {
+        parsed.pdf = Some(body.to_vec());
+        parsed.body.clear();
+    }
+    parsed
+}
+
 fn header_value<'a>(parsed: &'a ParsedHttp, key: &str) -> Option<&'a str> {
     let key_lower = ascii_lower_str(key);
     for (k, v) in parsed.headers.iter() {
@@ -2944,16 +2973,16 @@ fn render_plain_text(text: &str) -> Vec<String> {
     out
 }
 
-fn fetch_with_redirects(
-    start_url: &str,
-    pump_ui: &mut impl FnMut(),
-) -> Option<(ParsedHttp, String, usize)> {
+/// GET `start_url` on the background network worker, following up to
+/// `MAX_REDIRECTS` redirects by hand so the final URL is known.
+async fn fetch_with_redirects(start_url: &str) -> Option<(ParsedHttp, String, usize)> {
     let mut current_url = String::from(start_url.trim());
     let mut redirects = 0usize;
 
     loop {
-        let raw = crate::net::http_get_request(current_url.as_str(), pump_ui)?;
-        let parsed = parse_http_response(raw.as_str());
+        let request = crate::net::request::HttpRequest::get(current_url.as_str());
+        let raw = crate::net::worker::fetch(request)?.await?;
+        let parsed = parse_http_bytes(raw);
 
         let status = parsed.status_code.unwrap_or(0);
         if matches!(status, 301 | 302 | 303 | 307 | 308)
@@ -2970,6 +2999,17 @@ fn fetch_with_redirects(
     }
 }
 
+/// Download `url` (following redirects) and return it if the server sent a PDF.
+pub fn fetch_pdf(url: &str, pump_ui: &mut impl FnMut()) -> Result<Vec<u8>, String> {
+    let (mut parsed, _, _) = crate::executor::block_on_with(fetch_with_redirects(url), pump_ui)
+        .ok_or_else(|| String::from("no se pudo descargar"))?;
+    match (parsed.status_code.unwrap_or(0), parsed.pdf.take()) {
+        (200..=299, Some(data)) => Ok(data),
+        (200..=299, None) => Err(String::from("la respuesta no es un PDF")),
+        (status, _) => Err(format!("HTTP {}", status)),
+    }
+}
+
 fn response_blocked_for_reader(parsed: &ParsedHttp) -> bool {
     if matches!(parsed.status_code.unwrap_or(0), 401 | 403 | 429 | 451 | 503) {
         return true;
@@ -3026,11 +3066,323 @@ fn render_parsed_response(
     }
 }
 
+fn push_wrapped_prefixed(lines: &mut Vec<String>, prefix: &str, text: &str) {
+    let mut current = String::from(prefix);
+    for word in text.split_whitespace() {
+        if current.len() > prefix.len() && current.len() + 1 + word.len() > MAX_LINE_WIDTH {
+            push_line_raw(lines, current.as_str());
+            current.clear();
+            for _ in 0..prefix.len() {
+                current.push(' ');
+            }
+        }
+        if !current.trim().is_empty() && !current.ends_with(' ') {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.trim().is_empty() {
+        push_line_raw(lines, current.as_str());
+    }
+}
+
+/// Lay out a `text/gemini` document. Links keep the browser's `label <url>`
+/// convention with the URL resolved against `base_url`.
+fn render_gemtext(base_url: &str, text: &str) -> (Option<String>, Vec<String>) {
+    let ascii = to_ascii_sanitized(text);
+    let mut title = None;
+    let mut lines = Vec::new();
+    for line in parse_gemtext(ascii.as_str()) {
+        match line {
+            GemLine::Text(text) => {
+                if text.trim().is_empty() {
+                    push_line(&mut lines, "");
+                } else {
+                    push_wrapped_prefixed(&mut lines, "", text.as_str());
+                }
+            }
+            GemLine::Link { url, label } => {
+                let target = resolve_reference(base_url, url.as_str());
+                let label = if label.trim().is_empty() { target.as_str() } else { label.as_str() };
+                push_wrapped_prefixed(&mut lines, "=> ", format!("{} <{}>", label, target).as_str());
+            }
+            GemLine::Heading { level, text } => {
+                if title.is_none() && level == 1 {
+                    title = Some(String::from(text.trim()));
+                }
+                push_line(&mut lines, "");
+                if level == 1 {
+                    push_line(&mut lines, text.to_ascii_uppercase().as_str());
+                } else {
+                    push_line(&mut lines, text.as_str());
+                }
+            }
+            GemLine::ListItem(text) => push_wrapped_prefixed(&mut lines, "- ", text.as_str()),
+            GemLine::Quote(text) => push_wrapped_prefixed(&mut lines, "> ", text.as_str()),
+            GemLine::PreformatStart(_) => push_line(&mut lines, ""),
+            GemLine::Preformatted(text) => {
+                if text.trim().is_empty() {
+                    push_line_raw(&mut lines, "");
+                } else {
+                    push_line_raw(&mut lines, text.trim_end());
+                }
+            }
+        }
+        if lines.len() >= MAX_RENDER_LINES {
+            break;
+        }
+    }
+    if lines.iter().all(|l| l.is_empty()) {
+        lines = alloc::vec![String::from("(Sin contenido)")];
+    }
+    (title, lines)
+}
+
+fn fetch_and_render_gemini(url: &str, pump_ui: &mut impl FnMut()) -> Option<BrowserRenderOutput> {
+    let response = match crate::net::gemini::fetch(url, pump_ui) {
+        Ok(response) => response,
+        Err(err) => {
+            return Some(BrowserRenderOutput {
+                final_url: String::from(url),
+                status: String::from("Gemini: error"),
+                title: None,
+                lines: alloc::vec![format!("[Gemini] {}", err)],
+                surface: None,
+            });
+        }
+    };
+
+    let mut title = None;
+    let mut lines = Vec::new();
+    match response.status / 10 {
+        1 => {
+            lines.push(String::from("[Gemini] el servidor pide una entrada:"));
+            lines.push(to_ascii_sanitized(response.meta.as_str()));
+            lines.push(String::new());
+            lines.push(String::from(
+                "Agrega la respuesta a la URL tras '?' (espacios como %20) y vuelve a cargar.",
+            ));
+            if response.status == 11 {
+                lines.push(String::from("[Gemini] entrada sensible: no la compartas en capturas."));
+            }
+        }
+        2 => {
+            let (mime, _charset) = gemini_mime(response.meta.as_str());
+            let body = String::from_utf8_lossy(response.body.as_slice());
+            if mime == "text/gemini" {
+                let (doc_title, doc_lines) = render_gemtext(response.url.as_str(), &body);
+                title = doc_title;
+                lines = doc_lines;
+            } else if mime.starts_with("text/") {
+                lines = render_plain_text(&body);
+            } else {
+                lines.push(format!(
+                    "[Gemini] contenido {} ({} bytes) no se puede mostrar.",
+                    mime,
+                    response.body.len()
+                ));
+            }
+        }
+        3 => {
+            let target = resolve_reference(response.url.as_str(), response.meta.as_str());
+            lines.push(String::from("[Gemini] redireccion no seguida:"));
+            lines.push(format!("=> {} <{}>", target, target));
+        }
+        4 | 5 => {
+            let kind = if response.status / 10 == 4 { "temporal" } else { "permanente" };
+            lines.push(format!("[Gemini] error {} {}: {}", kind, response.status, response.meta));
+        }
+        6 => {
+            lines.push(format!(
+                "[Gemini] el servidor exige certificado de cliente ({}): {}",
+                response.status, response.meta
+            ));
+            lines.push(String::from("Los certificados de cliente no estan soportados."));
+        }
+        _ => lines.push(format!("[Gemini] estado desconocido {}", response.status)),
+    }
+
+    if response.redirects > 0 {
+        let mut prefix = Vec::new();
+        prefix.push(format!("[Gemini] redirects seguidos: {}", response.redirects));
+        prefix.push(format!("[Gemini] URL final: {}", response.url.as_str()));
+        prefix.push(String::new());
+        prefix.extend(lines);
+        lines = prefix;
+    }
+
+    Some(BrowserRenderOutput {
+        final_url: response.url,
+        status: format!("{} {}", response.status, to_ascii_sanitized(response.meta.as_str())),
+        title,
+        lines,
+        surface: None,
+    })
+}
+
+fn gopher_item_marker(item_type: u8) -> &'static str {
+    match item_type {
+        b'0' => "[TXT]",
+        b'1' => "[DIR]",
+        b'7' => "[BUSCAR]",
+        b'h' => "[WEB]",
+        b'g' | b'I' | b'p' => "[IMG]",
+        b'8' | b'T' => "[TELNET]",
+        b'4' | b'5' | b'6' | b'9' | b's' | b'd' => "[BIN]",
+        _ => "[?]",
+    }
+}
+
+fn render_gopher_menu(text: &str) -> Vec<String> {
+    let ascii = to_ascii_sanitized(text);
+    let mut lines = Vec::new();
+    for item in parse_gopher_menu(ascii.as_str()) {
+        match item.url() {
+            Some(target) => push_line(
+                &mut lines,
+                format!("{} {} <{}>", gopher_item_marker(item.item_type), item.display.trim(), target).as_str(),
+            ),
+            None if item.item_type == b'3' => {
+                push_line(&mut lines, format!("[ERROR] {}", item.display).as_str())
+            }
+            None => push_line_raw(&mut lines, item.display.trim_end()),
+        }
+        if lines.len() >= MAX_RENDER_LINES {
+            break;
+        }
+    }
+    if lines.is_empty() {
+        lines.push(String::from("(Menu vacio)"));
+    }
+    lines
+}
+
+fn fetch_and_render_gopher(url: &str, pump_ui: &mut impl FnMut()) -> Option<BrowserRenderOutput> {
+    let response = match crate::net::gopher::fetch(url, pump_ui) {
+        Ok(response) => response,
+        Err(err) => {
+            return Some(BrowserRenderOutput {
+                final_url: String::from(url),
+                status: String::from("Gopher: error"),
+                title: None,
+                lines: alloc::vec![format!("[Gopher] {}", err)],
+                surface: None,
+            });
+        }
+    };
+
+    let body = String::from_utf8_lossy(response.body.as_slice());
+    let mut title = None;
+    let mut surface = None;
+    let lines = match response.item_type {
+        b'7' if response.body.is_empty() => alloc::vec![
+            String::from("[Gopher] servidor de busqueda."),
+            String::from("Agrega los terminos a la URL tras '?' (espacios como %20) y vuelve a cargar."),
+        ],
+        b'1' | b'7' => render_gopher_menu(&body),
+        b'0' => render_plain_text(gopher_text_body(&body).as_str()),
+        b'h' => {
+            let (doc_title, doc_lines, doc_surface) = render_html_document(&body);
+            title = doc_title;
+            surface = doc_surface;
+            doc_lines
+        }
+        other => alloc::vec![format!(
+            "[Gopher] elemento tipo '{}' ({} bytes) no se puede mostrar.",
+            other as char,
+            response.body.len()
+        )],
+    };
+
+    Some(BrowserRenderOutput {
+        final_url: response.url,
+        status: format!("Gopher tipo {} ({} bytes)", response.item_type as char, response.body.len()),
+        title,
+        lines,
+        surface,
+    })
+}
+
+/// Show one page of a PDF (`#page=N`, else the first) as the surface,
+/// with the page text as lines for the reader view.
+fn render_pdf_response(data: Vec<u8>, mut final_url: String, fragment: &str) -> BrowserRenderOutput {
+    let size = data.len();
+    let wanted = crate::pdf::page_fragment(fragment.trim_start_matches('#'));
+    final_url.push_str(fragment);
+    let mut lines = Vec::new();
+    let doc = match crate::pdf::Document::parse(data) {
+        Ok(doc) => doc,
+        Err(e) => {
+            push_line(&mut lines, format!("[PDF] no se pudo abrir: {}", e).as_str());
+            return BrowserRenderOutput {
+                final_url,
+                status: String::from("PDF invalido"),
+                title: None,
+                lines,
+                surface: None,
+            };
+        }
+    };
+    let count = doc.page_count();
+    let index = wanted.min(count - 1);
+    push_line(&mut lines, format!("[PDF] {} paginas, {} bytes", count, size).as_str());
+    push_line(&mut lines, "[PDF] #page=N en la URL elige la pagina; 'pdf open <url>' abre el visor.");
+    push_line(&mut lines, "");
+    let surface = match doc.render_page(index, 100) {
+        Ok(page) => {
+            for line in page.text.lines() {
+                push_line(&mut lines, crate::pdf::ascii_text(line).as_str());
+            }
+            Some(BrowserRenderSurface {
+                source: String::from("pdf"),
+                width: page.width,
+                height: page.height,
+                pixels: page.pixels,
+            })
+        }
+        Err(e) => {
+            push_line(&mut lines, format!("[PDF] no se pudo dibujar la pagina: {}", e).as_str());
+            None
+        }
+    };
+    BrowserRenderOutput {
+        final_url,
+        status: format!("PDF: pagina {}/{}", index + 1, count),
+        title: doc.title().map(|t| crate::pdf::ascii_text(t.as_str())),
+        lines,
+        surface,
+    }
+}
+
+/// Fetch and render `url` for a caller that waits for the page: the HTTP
+/// requests still go through the network worker, with `pump_ui` keeping the
+/// desktop alive meanwhile. The builtin browser uses `load` instead.
 pub fn fetch_and_render(url: &str, pump_ui: &mut impl FnMut()) -> Option<BrowserRenderOutput> {
     let base_url = String::from(url.trim());
     if base_url.is_empty() {
         return None;
     }
+    if starts_with_ignore_ascii_case(base_url.as_str(), "gemini://") {
+        return fetch_and_render_gemini(base_url.as_str(), pump_ui);
+    }
+    if starts_with_ignore_ascii_case(base_url.as_str(), "gopher://") {
+        return fetch_and_render_gopher(base_url.as_str(), pump_ui);
+    }
+    crate::executor::block_on_with(load(base_url), pump_ui)
+}
+
+/// Fetch and render an HTTP(S) page without blocking: run it as an executor
+/// task (`executor::spawn_joinable`) and collect the page when it is done.
+pub async fn load(url: String) -> Option<BrowserRenderOutput> {
+    let base_url = String::from(url.trim());
+    if base_url.is_empty() {
+        return None;
+    }
+    // Fragments never go on the wire; `#page=N` picks the page of a PDF.
+    let (base_url, fragment) = match base_url.find('#') {
+        Some(at) => (String::from(&base_url[..at]), String::from(&base_url[at..])),
+        None => (base_url, String::new()),
+    };
 
     // Native route first: direct fetch without host/bridge dependency.
     let _ = crate::net::set_https_mode_disabled();
@@ -3040,13 +3392,13 @@ pub fn fetch_and_render(url: &str, pump_ui: &mut impl FnMut()) -> Option<Browser
     let mut used_reader_proxy = false;
     let mut reader_note: Option<String> = None;
     let (mut parsed, mut final_url, mut redirects) =
-        if let Some((parsed, final_url, redirects)) = fetch_with_redirects(base_url.as_str(), pump_ui)
+        if let Some((parsed, final_url, redirects)) = fetch_with_redirects(base_url.as_str()).await
         {
             (parsed, final_url, redirects)
         } else if should_try_reader_proxy(base_url.as_str()) {
             let proxy_url = build_reader_proxy_url(base_url.as_str())?;
             let (proxy_parsed, _proxy_final, proxy_redirects) =
-                fetch_with_redirects(proxy_url.as_str(), pump_ui)?;
+                fetch_with_redirects(proxy_url.as_str()).await?;
             used_reader_proxy = true;
             reader_note = Some(String::from(
                 "[Render] fetch directo fallo; usando fallback reader-proxy.",
@@ -3056,6 +3408,10 @@ pub fn fetch_and_render(url: &str, pump_ui: &mut impl FnMut()) -> Option<Browser
             return None;
         };
 
+    if let Some(data) = parsed.pdf.take() {
+        return Some(render_pdf_response(data, final_url, fragment.as_str()));
+    }
+
     let (mut title, mut lines, mut surface) = render_parsed_response(&parsed);
 
     if should_try_reader_proxy(base_url.as_str()) && !used_reader_proxy {
@@ -3064,7 +3420,7 @@ pub fn fetch_and_render(url: &str, pump_ui: &mut impl FnMut()) -> Option<Browser
         if blocked || unusable {
             if let Some(proxy_url) = build_reader_proxy_url(base_url.as_str()) {
                 if let Some((proxy_parsed, _proxy_final, proxy_redirects)) =
-                    fetch_with_redirects(proxy_url.as_str(), pump_ui)
+                    fetch_with_redirects(proxy_url.as_str()).await
                 {
                     let (proxy_title, proxy_lines, proxy_surface) =
                         render_parsed_response(&proxy_parsed);
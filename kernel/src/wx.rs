@@ -0,0 +1,349 @@
+//! Write xor execute.
+//!
+//! `init` turns on EFER.NXE and CR0.WP, then maps the kernel image by PE
+//! section: code read-only and executable, read-only data and headers
+//! read-only, data and bss writable, and only code executable. The whole heap
+//! becomes non-executable. Firmware memory keeps the mapping it came with,
+//! since boot services still run from it.
+//!
+//! Linux guests live in the heap. Their images are `PageBuffer`s whose `PF_X`
+//! segments become read-only and executable, and mmap regions follow
+//! `PROT_EXEC` the same way. A request for memory that is writable and
+//! executable at once fails with EACCES, so a JIT maps its code writable and
+//! flips it with `mprotect`. `security.wx false` lets such requests through
+//! as RWX pages, starting with the next mapping.
+
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use crate::println;
+
+pub const KEY: &str = "security.wx";
+const PAGE_SIZE: u64 = 4096;
+const IMAGE_SCN_MEM_EXECUTE: u32 = 0x2000_0000;
+const IMAGE_SCN_MEM_WRITE: u32 = 0x8000_0000;
+const PE32_PLUS_MAGIC: u16 = 0x20B;
+
+static REJECTED: AtomicU64 = AtomicU64::new(0);
+
+fn align_up(value: u64) -> u64 {
+    (value + PAGE_SIZE - 1) & !(PAGE_SIZE - 1)
+}
+
+/// Whether guest W+X requests are refused.
+pub fn enforced() -> bool {
+    crate::paging::nx_enabled() && crate::config::get_bool(KEY, true)
+}
+
+/// Whether a guest may map memory with these rights; counts refusals.
+pub fn permits(writable: bool, executable: bool) -> bool {
+    if writable && executable && enforced() {
+        REJECTED.fetch_add(1, Ordering::Relaxed);
+        return false;
+    }
+    true
+}
+
+/// Guest W+X requests refused since boot.
+pub fn rejected() -> u64 {
+    REJECTED.load(Ordering::Relaxed)
+}
+
+/// Make a page-aligned guest range read-only and executable (writable too
+/// with the switch off), or writable and non-executable again. Guest memory
+/// is identity-mapped heap, so this edits the kernel map that every process
+/// PML4 shares. Returns whether the range is executable now.
+pub fn set_guest_exec(addr: u64, len: u64, executable: bool) -> bool {
+    if !crate::paging::nx_enabled() || len == 0 {
+        return false;
+    }
+    let writable = !executable || !enforced();
+    let end = align_up(addr + len);
+    crate::paging::protect_kernel_range(addr & !(PAGE_SIZE - 1), end, writable, executable).is_ok() && executable
+}
+
+/// Zeroed, page-aligned heap buffer whose pages may be made executable.
+/// Dropping it returns the pages to the heap writable and non-executable.
+pub struct PageBuffer {
+    ptr: *mut u8,
+    len: usize,
+    executable: AtomicBool,
+}
+
+// Owns its allocation like a `Vec<u8>`.
+unsafe impl Send for PageBuffer {}
+unsafe impl Sync for PageBuffer {}
+
+impl PageBuffer {
+    fn layout(len: usize) -> Option<core::alloc::Layout> {
+        core::alloc::Layout::from_size_align(align_up(len.max(1) as u64) as usize, PAGE_SIZE as usize).ok()
+    }
+
+    pub fn zeroed(len: usize) -> Option<Self> {
+        let ptr = unsafe { alloc::alloc::alloc_zeroed(Self::layout(len)?) };
+        if ptr.is_null() {
+            return None;
+        }
+        Some(Self {
+            ptr,
+            len,
+            executable: AtomicBool::new(false),
+        })
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+
+    /// Make the pages covering `[start, end)` read-only and executable.
+    pub fn make_executable(&self, start: usize, end: usize) -> bool {
+        let end = end.min(self.len);
+        if start >= end {
+            return false;
+        }
+        let base = self.ptr as u64;
+        let done = set_guest_exec(base + start as u64, (end - start) as u64, true);
+        if done {
+            self.executable.store(true, Ordering::Release);
+        }
+        done
+    }
+}
+
+impl Deref for PageBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl DerefMut for PageBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.as_mut_slice()
+    }
+}
+
+impl Drop for PageBuffer {
+    fn drop(&mut self) {
+        let Some(layout) = Self::layout(self.len) else {
+            return;
+        };
+        if self.executable.load(Ordering::Acquire) {
+            set_guest_exec(self.ptr as u64, layout.size() as u64, false);
+        }
+        unsafe { alloc::alloc::dealloc(self.ptr, layout) };
+    }
+}
+
+/// A PE section, relative to the image base.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Section {
+    rva: u64,
+    len: u64,
+    writable: bool,
+    executable: bool,
+}
+
+/// Headers of a PE32+ image.
+struct PeLayout {
+    section_alignment: u32,
+    headers_len: u64,
+    sections: Vec<Section>,
+}
+
+fn read_u16(raw: &[u8], off: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(raw.get(off..off + 2)?.try_into().ok()?))
+}
+
+fn read_u32(raw: &[u8], off: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(raw.get(off..off + 4)?.try_into().ok()?))
+}
+
+fn parse_pe(image: &[u8]) -> Option<PeLayout> {
+    if image.get(..2)? != b"MZ" {
+        return None;
+    }
+    let pe = read_u32(image, 0x3C)? as usize;
+    if image.get(pe..pe + 4)? != b"PE\0\0" {
+        return None;
+    }
+    let coff = pe + 4;
+    let count = read_u16(image, coff + 2)? as usize;
+    let optional_len = read_u16(image, coff + 16)? as usize;
+    let optional = coff + 20;
+    if read_u16(image, optional)? != PE32_PLUS_MAGIC {
+        return None;
+    }
+    let section_alignment = read_u32(image, optional + 32)?;
+    let headers_len = read_u32(image, optional + 60)? as u64;
+    let table = optional + optional_len;
+    let mut sections = Vec::with_capacity(count);
+    for index in 0..count {
+        let entry = table + index * 40;
+        let len = read_u32(image, entry + 8)? as u64;
+        let rva = read_u32(image, entry + 12)? as u64;
+        let flags = read_u32(image, entry + 36)?;
+        if len == 0 {
+            continue;
+        }
+        sections.push(Section {
+            rva,
+            len,
+            writable: flags & IMAGE_SCN_MEM_WRITE != 0,
+            executable: flags & IMAGE_SCN_MEM_EXECUTE != 0,
+        });
+    }
+    Some(PeLayout {
+        section_alignment,
+        headers_len,
+        sections,
+    })
+}
+
+pub(crate) fn kernel_image() -> Option<(u64, u64)> {
+    use uefi::boot;
+    use uefi::proto::loaded_image::LoadedImage;
+
+    let loaded = boot::open_protocol_exclusive::<LoadedImage>(boot::image_handle()).ok()?;
+    let (base, size) = loaded.info();
+    Some((base as u64, size))
+}
+
+/// Map the kernel image by section. Returns how many sections it covered.
+fn protect_kernel_image() -> Result<usize, &'static str> {
+    let (base, size) = kernel_image().ok_or("sin LoadedImage")?;
+    let image = unsafe { core::slice::from_raw_parts(base as *const u8, size as usize) };
+    let layout = parse_pe(image).ok_or("cabecera PE invalida")?;
+    if base % PAGE_SIZE != 0 || layout.section_alignment as u64 % PAGE_SIZE != 0 {
+        return Err("secciones sin alinear a pagina");
+    }
+    if layout
+        .sections
+        .iter()
+        .any(|section| section.writable && section.executable)
+    {
+        return Err("seccion escribible y ejecutable");
+    }
+    crate::paging::protect_kernel_range(base, base + align_up(layout.headers_len), false, false)?;
+    for section in layout.sections.iter() {
+        let start = base + section.rva;
+        let end = base + align_up(section.rva + section.len);
+        crate::paging::protect_kernel_range(start, end, section.writable, section.executable)?;
+    }
+    Ok(layout.sections.len())
+}
+
+/// MpServices procedure: NXE and WP on an AP the firmware still runs.
+extern "efiapi" fn prepare_ap(_arg: *mut core::ffi::c_void) {
+    crate::paging::enable_nx();
+    crate::paging::enable_write_protect();
+}
+
+/// Enable NX and write protection and remap the kernel image and heap.
+/// Runs on the BSP before the APs start. Firmware-run APs get NXE here, since
+/// an NX bit is a reserved-bit fault without it; the bare-metal trampoline
+/// sets it on its own.
+pub fn init() {
+    let nx = crate::paging::enable_nx();
+    crate::paging::enable_write_protect();
+    if nx {
+        crate::smp::run_on_firmware_aps(prepare_ap);
+    }
+    let image = protect_kernel_image();
+    let (heap_start, heap_len) = crate::allocator::heap_range();
+    let heap = if nx && heap_len > 0 {
+        crate::paging::protect_kernel_range(heap_start as u64, align_up((heap_start + heap_len) as u64), true, false)
+            .map(|_| ())
+    } else {
+        Ok(())
+    };
+    match image {
+        Ok(sections) => println(alloc::format!("W^X: {} secciones del kernel protegidas", sections).as_str()),
+        Err(err) => println(alloc::format!("W^X: imagen del kernel sin proteger ({})", err).as_str()),
+    }
+    if !nx {
+        println("W^X: la CPU no tiene NX; la memoria sigue siendo ejecutable");
+    } else if let Err(err) = heap {
+        println(alloc::format!("W^X: heap sin NX ({})", err).as_str());
+    } else {
+        println(alloc::format!("W^X: NX activo, heap de {} MiB sin ejecucion", heap_len >> 20).as_str());
+    }
+}
+
+crate::selftest::kernel_tests! {
+    "wx";
+
+    fn parses_pe_sections() {
+        let mut image = alloc::vec![0u8; 0x200];
+        image[..2].copy_from_slice(b"MZ");
+        image[0x3C..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        image[0x40..0x44].copy_from_slice(b"PE\0\0");
+        image[0x46..0x48].copy_from_slice(&2u16.to_le_bytes());
+        image[0x54..0x56].copy_from_slice(&0xF0u16.to_le_bytes());
+        image[0x58..0x5A].copy_from_slice(&PE32_PLUS_MAGIC.to_le_bytes());
+        image[0x78..0x7C].copy_from_slice(&0x1000u32.to_le_bytes());
+        image[0x94..0x98].copy_from_slice(&0x400u32.to_le_bytes());
+        let table = 0x58 + 0xF0;
+        for (index, (rva, len, flags)) in
+            [(0x1000u32, 0x2345u32, IMAGE_SCN_MEM_EXECUTE), (0x4000, 0x10, IMAGE_SCN_MEM_WRITE)].iter().enumerate()
+        {
+            let entry = table + index * 40;
+            image[entry + 8..entry + 12].copy_from_slice(&len.to_le_bytes());
+            image[entry + 12..entry + 16].copy_from_slice(&rva.to_le_bytes());
+            image[entry + 36..entry + 40].copy_from_slice(&flags.to_le_bytes());
+        }
+        let layout = parse_pe(&image).ok_or("cabecera no reconocida")?;
+        crate::selftest::ensure_eq(layout.section_alignment, 0x1000, "alineacion")?;
+        crate::selftest::ensure_eq(layout.headers_len, 0x400, "cabeceras")?;
+        crate::selftest::ensure_eq(
+            layout.sections,
+            alloc::vec![
+                Section { rva: 0x1000, len: 0x2345, writable: false, executable: true },
+                Section { rva: 0x4000, len: 0x10, writable: true, executable: false },
+            ],
+            "secciones",
+        )
+    }
+
+    fn kernel_text_and_data() {
+        if !crate::paging::nx_enabled() {
+            return Ok(());
+        }
+        let code = init as *const () as u64;
+        crate::selftest::ensure_eq(crate::paging::kernel_page_access(code), Some((false, true)), "codigo")?;
+        let data = core::ptr::addr_of!(REJECTED) as u64;
+        crate::selftest::ensure_eq(crate::paging::kernel_page_access(data), Some((true, false)), "datos")?;
+        let heap = alloc::boxed::Box::new(0u64);
+        let addr = &*heap as *const u64 as u64;
+        crate::selftest::ensure_eq(crate::paging::kernel_page_access(addr), Some((true, false)), "heap")
+    }
+
+    fn page_buffer_flips_back() {
+        if !crate::paging::nx_enabled() {
+            return Ok(());
+        }
+        let buffer = PageBuffer::zeroed(2 * PAGE_SIZE as usize).ok_or("sin memoria")?;
+        let addr = buffer.as_ptr() as u64;
+        crate::selftest::ensure(buffer.make_executable(0, 1), "sin ejecucion")?;
+        let access = crate::paging::kernel_page_access(addr);
+        crate::selftest::ensure_eq(access, Some((!enforced(), true)), "pagina ejecutable")?;
+        crate::selftest::ensure_eq(
+            crate::paging::kernel_page_access(addr + PAGE_SIZE),
+            Some((true, false)),
+            "segunda pagina",
+        )?;
+        drop(buffer);
+        crate::selftest::ensure_eq(crate::paging::kernel_page_access(addr), Some((true, false)), "tras liberar")
+    }
+
+    fn rwx_requests() {
+        crate::selftest::ensure(permits(true, false) && permits(false, true), "W o X")?;
+        crate::selftest::ensure_eq(permits(true, true), !enforced(), "W y X")
+    }
+}
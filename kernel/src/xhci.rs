@@ -1,11 +1,205 @@
-use crate::pci::{PciDevice, read_bar};
+//! xHCI host controllers. The firmware's USB stack drives them: devices are
+//! reached through its EFI_USB_IO handles (see `usbhid`). The kernel only
+//! reads the root hub port status registers, so `usb` notices a device being
+//! plugged in or pulled out without walking the handle database every frame.
+//! The change bits are never written; they belong to the firmware's hub
+//! driver, which acknowledges them when it enumerates the port.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::pci::{PciDevice, PciDriver, PciMatch, read_bar, read_config};
 use crate::println;
+use crate::spinlock::SpinLock;
+
+pub static PCI_DRIVER: PciDriver = PciDriver {
+    name: "xhci",
+    ids: &[PciMatch::class(0x0C, 0x03)],
+    probe: init,
+};
+
+const REG_CAPLENGTH: u64 = 0x00;
+const REG_HCSPARAMS1: u64 = 0x04;
+const PORT_REGS_OFFSET: u64 = 0x400;
+const PORT_REGS_STRIDE: u64 = 0x10;
+
+const PORTSC_CCS: u32 = 1 << 0;
+const PORTSC_PED: u32 = 1 << 1;
+const PORTSC_SPEED_SHIFT: u32 = 10;
+const PORTSC_SPEED_MASK: u32 = 0xF;
+const PORTSC_CSC: u32 = 1 << 17;
+
+struct Controller {
+    name: String,
+    op_base: u64,
+    /// (connected, connect change) of every port at the last look.
+    last: Vec<(bool, bool)>,
+}
+
+static CONTROLLERS: SpinLock<Vec<Controller>> = SpinLock::new(Vec::new());
+
+static GUARD_OPS: crate::driver_guard::GuardOps = crate::driver_guard::GuardOps { name: "xhci", release };
+
+/// After a fault: forget the controllers, so `usb` goes back to walking the
+/// firmware's handles on its idle timer. The firmware keeps driving them.
+fn release() {
+    unsafe { CONTROLLERS.force_unlock() };
+    CONTROLLERS.lock().clear();
+}
+
+static PM_OPS: crate::pm::PmOps = crate::pm::PmOps {
+    name: "xhci",
+    suspend,
+    resume,
+};
+
+fn suspend() -> Result<(), &'static str> {
+    Ok(())
+}
+
+/// Whatever was plugged or pulled while asleep shows up as a changed
+/// baseline; take a fresh one and let `usb` walk the handles again.
+fn resume() {
+    for controller in CONTROLLERS.lock().iter_mut() {
+        let op_base = controller.op_base;
+        for (port, last) in controller.last.iter_mut().enumerate() {
+            let status = read_port(op_base, port);
+            *last = (status.connected, status.connect_changed);
+        }
+    }
+    crate::usb::request_rescan();
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct PortStatus {
+    pub connected: bool,
+    pub enabled: bool,
+    /// Protocol speed ID: 1 full, 2 low, 3 high, 4 SuperSpeed, 5 SuperSpeedPlus.
+    pub speed: u8,
+    pub connect_changed: bool,
+}
+
+pub fn decode_portsc(value: u32) -> PortStatus {
+    PortStatus {
+        connected: value & PORTSC_CCS != 0,
+        enabled: value & PORTSC_PED != 0,
+        speed: ((value >> PORTSC_SPEED_SHIFT) & PORTSC_SPEED_MASK) as u8,
+        connect_changed: value & PORTSC_CSC != 0,
+    }
+}
+
+pub fn speed_name(speed: u8) -> &'static str {
+    match speed {
+        1 => "full (12 Mb/s)",
+        2 => "low (1.5 Mb/s)",
+        3 => "high (480 Mb/s)",
+        4 => "super (5 Gb/s)",
+        5 => "super+ (10 Gb/s)",
+        _ => "?",
+    }
+}
+
+unsafe fn read_u32(addr: u64) -> u32 {
+    core::ptr::read_volatile(addr as *const u32)
+}
+
+fn read_port(op_base: u64, port: usize) -> PortStatus {
+    let addr = op_base + PORT_REGS_OFFSET + PORT_REGS_STRIDE * port as u64;
+    decode_portsc(unsafe { read_u32(addr) })
+}
 
 pub fn init(device: PciDevice) {
-    let bar0 = unsafe { read_bar(device.bus, device.slot, device.func, 0) };
-    if let Some(_) = bar0 {
-        println("xHCI: Initialized (Stub). MMIO Base found.");
-    } else {
+    crate::driver_guard::register(&GUARD_OPS);
+    // UHCI/OHCI/EHCI (and USB4 hosts) share the class; they stay with the firmware.
+    let prog_if = (unsafe { read_config(device.bus, device.slot, device.func, 0x08) } >> 8) & 0xFF;
+    if prog_if != 0x30 {
+        println("USB: Controller is not xHCI, left to the firmware.");
+        return;
+    }
+    let Some(base) = (unsafe { read_bar(device.bus, device.slot, device.func, 0) }) else {
         println("xHCI: Failed to find BAR0.");
+        return;
+    };
+    let cap_length = unsafe { read_u32(base + REG_CAPLENGTH) } & 0xFF;
+    let max_ports = ((unsafe { read_u32(base + REG_HCSPARAMS1) } >> 24) & 0xFF) as usize;
+    if cap_length == 0 || max_ports == 0 {
+        println("xHCI: Registers not readable.");
+        return;
+    }
+    let op_base = base + cap_length as u64;
+    let last = (0..max_ports)
+        .map(|port| {
+            let status = read_port(op_base, port);
+            (status.connected, status.connect_changed)
+        })
+        .collect();
+    let name = crate::device::pci_name(device.bus, device.slot, device.func);
+    crate::device::set_driver(crate::device::pci_function(&device), Some("xhci"));
+    println(alloc::format!("xHCI: {} root ports at {:#x}.", max_ports, base).as_str());
+    CONTROLLERS.lock().push(Controller { name, op_base, last });
+    crate::pm::register(&PM_OPS);
+}
+
+pub fn controller_count() -> usize {
+    CONTROLLERS.lock().len()
+}
+
+/// Ports whose connection changed since the last call.
+pub fn port_changes() -> usize {
+    crate::driver_guard::call(PCI_DRIVER.name, read_port_changes).unwrap_or(0)
+}
+
+fn read_port_changes() -> usize {
+    let mut changed = 0;
+    for controller in CONTROLLERS.lock().iter_mut() {
+        let op_base = controller.op_base;
+        for (port, last) in controller.last.iter_mut().enumerate() {
+            let status = read_port(op_base, port);
+            let now = (status.connected, status.connect_changed);
+            if now != *last {
+                *last = now;
+                changed += 1;
+            }
+        }
+    }
+    changed
+}
+
+/// Connected root ports: (controller, 1-based port, status).
+pub fn connected_ports() -> Vec<(String, usize, PortStatus)> {
+    crate::driver_guard::call(PCI_DRIVER.name, read_connected_ports).unwrap_or_default()
+}
+
+fn read_connected_ports() -> Vec<(String, usize, PortStatus)> {
+    let mut out = Vec::new();
+    for controller in CONTROLLERS.lock().iter() {
+        for port in 0..controller.last.len() {
+            let status = read_port(controller.op_base, port);
+            if status.connected {
+                out.push((controller.name.clone(), port + 1, status));
+            }
+        }
+    }
+    out
+}
+
+crate::selftest::kernel_tests! {
+    "xhci";
+
+    fn port_status_bits_decode() {
+        // Connected, enabled, high speed, connect status change pending.
+        let status = decode_portsc(PORTSC_CCS | PORTSC_PED | (3 << PORTSC_SPEED_SHIFT) | PORTSC_CSC | (1 << 9));
+        crate::selftest::ensure_eq(
+            status,
+            PortStatus {
+                connected: true,
+                enabled: true,
+                speed: 3,
+                connect_changed: true,
+            },
+            "puerto ocupado",
+        )?;
+        crate::selftest::ensure_eq(decode_portsc(1 << 9), PortStatus::default(), "puerto vacio con energia")?;
+        crate::selftest::ensure_eq(speed_name(4), "super (5 Gb/s)", "velocidad")
     }
 }
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use redux_netparse::http::http_decode_chunked_body;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(body) = http_decode_chunked_body(data) {
+        assert!(body.len() <= data.len());
+    }
+});
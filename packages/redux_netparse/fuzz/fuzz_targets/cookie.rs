@@ -0,0 +1,21 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use redux_netparse::cookie::{http_cookie_domain_matches, http_parse_set_cookie};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = core::str::from_utf8(data) else {
+        return;
+    };
+    // `value\0host\0path`, so the request context is fuzzed too.
+    let mut parts = text.splitn(3, '\0');
+    let value = parts.next().unwrap_or("");
+    let host = parts.next().unwrap_or("example.com");
+    let path = parts.next().unwrap_or("/");
+    for is_https in [false, true] {
+        if let Some(cookie) = http_parse_set_cookie(value, host, path, is_https, 0) {
+            assert!(http_cookie_domain_matches(host, &cookie.domain, cookie.host_only));
+            assert!(!cookie.secure || is_https);
+        }
+    }
+});
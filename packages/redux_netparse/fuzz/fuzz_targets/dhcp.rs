@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use redux_netparse::dhcp::{dhcp_hostname, parse_dhcp_ack_options, DhcpLeaseRecord};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_dhcp_ack_options(data);
+    let text = String::from_utf8_lossy(data);
+    if let Some(lease) = DhcpLeaseRecord::parse(&text) {
+        assert_eq!(DhcpLeaseRecord::parse(&lease.to_text()), Some(lease));
+    }
+    if let Some(name) = dhcp_hostname(&text) {
+        assert!(!name.is_empty() && name.len() <= 63);
+    }
+});
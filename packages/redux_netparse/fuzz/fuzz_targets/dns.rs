@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use redux_netparse::dns::{instance_label, parse_dns_message, txt_value, DnsData};
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(msg) = parse_dns_message(data) {
+        for record in msg.records.iter() {
+            let _ = instance_label(&record.name, "_ipp._tcp.local");
+            if let DnsData::Txt(entries) = &record.data {
+                let _ = txt_value(entries, "rp");
+            }
+        }
+    }
+});
@@ -0,0 +1,21 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use redux_netparse::ftp::{
+    ftp_arg_is_safe, parse_epsv_reply, parse_ftp_reply, parse_ftp_target, parse_list, parse_pasv_reply,
+    parse_pwd_reply,
+};
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(reply) = parse_ftp_reply(data) {
+        assert!(reply.consumed <= data.len());
+        let _ = parse_pasv_reply(&reply.text);
+        let _ = parse_epsv_reply(&reply.text);
+        let _ = parse_pwd_reply(&reply.text);
+    }
+    let text = String::from_utf8_lossy(data);
+    let _ = parse_list(&text);
+    if let Some(target) = parse_ftp_target(&text) {
+        assert!(ftp_arg_is_safe(&target.path));
+    }
+});
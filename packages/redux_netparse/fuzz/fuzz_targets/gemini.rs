@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use redux_netparse::gemini::{parse_gemini_header, parse_gemini_url, parse_gemtext, GemLine};
+use redux_netparse::url::resolve_reference;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(head) = parse_gemini_header(data) {
+        assert!(head.body_offset <= data.len());
+    }
+    let text = String::from_utf8_lossy(data);
+    let _ = parse_gemini_url(&text);
+    for line in parse_gemtext(&text) {
+        if let GemLine::Link { url, .. } = line {
+            let _ = resolve_reference("gemini://example.org/a/b", &url);
+        }
+    }
+});
@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use redux_netparse::gopher::{gopher_text_body, parse_gopher_menu, parse_gopher_url};
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    for item in parse_gopher_menu(&text) {
+        if let Some(url) = item.url() {
+            let _ = parse_gopher_url(&url);
+        }
+    }
+    let _ = parse_gopher_url(&text);
+    let _ = gopher_text_body(&text);
+});
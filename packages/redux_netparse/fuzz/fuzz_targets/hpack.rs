@@ -0,0 +1,25 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use redux_netparse::hpack::{
+    hpack_decode_header_block, HpackDynamicTable, HPACK_DYNAMIC_TABLE_ABSOLUTE_MAX,
+};
+
+// The first byte splits the input into two header blocks decoded against the
+// same dynamic table, so table-size updates and eviction carry across blocks.
+fuzz_target!(|data: &[u8]| {
+    let Some((&split, rest)) = data.split_first() else {
+        return;
+    };
+    let split = (split as usize).min(rest.len());
+    let (first, second) = rest.split_at(split);
+
+    let mut table = HpackDynamicTable::new();
+    for block in [first, second] {
+        let mut headers = Vec::new();
+        let mut status = None;
+        let _ = hpack_decode_header_block(block, &mut table, &mut headers, &mut status);
+        assert!(table.size() <= table.max_size());
+        assert!(table.max_size() <= HPACK_DYNAMIC_TABLE_ABSOLUTE_MAX);
+    }
+});
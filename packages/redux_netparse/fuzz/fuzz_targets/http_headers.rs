@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use redux_netparse::http::{header_first, header_values, parse_http_headers};
+
+fuzz_target!(|data: &[u8]| {
+    let parsed = parse_http_headers(data);
+    assert!(parsed.body_offset <= data.len());
+    for (name, _) in parsed.headers.iter() {
+        assert!(header_first(&parsed.headers, name).is_some());
+        assert!(!header_values(&parsed.headers, name).is_empty());
+    }
+});
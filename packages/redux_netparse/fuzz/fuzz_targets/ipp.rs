@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use redux_netparse::ipp::{ipp_http_url, parse_ipp_message};
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(msg) = parse_ipp_message(data) {
+        assert!(msg.data_offset <= data.len());
+        let _ = msg.strings(0, "document-format-supported");
+    }
+    let _ = ipp_http_url(&String::from_utf8_lossy(data));
+});
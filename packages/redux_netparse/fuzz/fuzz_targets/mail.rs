@@ -0,0 +1,26 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use redux_netparse::imap::{parse_exists, parse_fetch, parse_imap_greeting, parse_imap_response};
+use redux_netparse::mime::{base64_decode, decode_encoded_words, extract_address, parse_message};
+use redux_netparse::smtp::{dot_stuff, parse_smtp_reply};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_message(data);
+    let _ = base64_decode(data);
+    let _ = parse_imap_greeting(data);
+    if let Some(response) = parse_imap_response(data, "A1") {
+        assert!(response.consumed <= data.len());
+        for line in response.untagged.iter() {
+            let _ = parse_exists(line);
+            let _ = parse_fetch(line);
+        }
+    }
+    if let Some(reply) = parse_smtp_reply(data) {
+        assert!(reply.consumed <= data.len());
+    }
+    assert!(dot_stuff(data).ends_with(b".\r\n"));
+    let text = String::from_utf8_lossy(data);
+    let _ = decode_encoded_words(&text);
+    let _ = extract_address(&text);
+});
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use redux_netparse::neighbor::{mac_text, observe_frame, parse_mac};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = observe_frame(data);
+    let text = String::from_utf8_lossy(data);
+    if let Some(mac) = parse_mac(&text) {
+        assert_eq!(parse_mac(&mac_text(&mac)), Some(mac));
+    }
+});
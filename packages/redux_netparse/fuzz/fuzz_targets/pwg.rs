@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use redux_netparse::pwg::{decode_pwg_page, encode_pwg_page};
+
+fuzz_target!(|data: &[u8]| {
+    if let Some((page, pixels, used)) = decode_pwg_page(data) {
+        assert!(used <= data.len());
+        // Whatever decodes re-encodes to the same pixels.
+        let bpl = page.bytes_per_line();
+        let encoded = encode_pwg_page(&page, &mut |y, line| {
+            line.copy_from_slice(&pixels[y as usize * bpl..(y as usize + 1) * bpl])
+        });
+        assert_eq!(decode_pwg_page(&encoded).map(|(_, p, _)| p), Some(pixels));
+    }
+});
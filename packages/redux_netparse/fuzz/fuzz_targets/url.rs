@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use redux_netparse::url::{extract_url_host, parse_url};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = core::str::from_utf8(data) else {
+        return;
+    };
+    if let Some((host, _port, path)) = parse_url(text) {
+        assert!(text.len() >= host.len() + path.len());
+    }
+    let _ = extract_url_host(text);
+});
@@ -0,0 +1,145 @@
+//! `Set-Cookie` parsing and the RFC 6265 domain/path matching rules.
+
+use alloc::format;
+use alloc::string::String;
+
+use crate::ascii_lowercase;
+
+/// Ticks per second of the kernel timer used for `Max-Age`.
+pub const COOKIE_TICKS_PER_SECOND: u64 = 100;
+
+#[derive(Clone)]
+pub struct HttpCookieEntry {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    pub path: String,
+    pub secure: bool,
+    pub host_only: bool,
+    pub expires_at_ticks: Option<u64>,
+}
+
+pub fn http_cookie_domain_matches(host: &str, cookie_domain: &str, host_only: bool) -> bool {
+    let host_l = ascii_lowercase(host);
+    let dom_l = ascii_lowercase(cookie_domain);
+    if host_only {
+        return host_l == dom_l;
+    }
+    if host_l == dom_l {
+        return true;
+    }
+    host_l.ends_with(format!(".{}", dom_l).as_str())
+}
+
+pub fn http_cookie_path_matches(request_path: &str, cookie_path: &str) -> bool {
+    if cookie_path.is_empty() || cookie_path == "/" {
+        return true;
+    }
+    if !request_path.starts_with(cookie_path) {
+        return false;
+    }
+    if request_path.len() == cookie_path.len() {
+        return true;
+    }
+    cookie_path.ends_with('/') || request_path.as_bytes()[cookie_path.len()] == b'/'
+}
+
+pub fn http_cookie_default_path(request_path: &str) -> String {
+    if !request_path.starts_with('/') {
+        return String::from("/");
+    }
+    if request_path == "/" {
+        return String::from("/");
+    }
+    let bytes = request_path.as_bytes();
+    let mut i = bytes.len();
+    while i > 0 {
+        i -= 1;
+        if bytes[i] == b'/' {
+            if i == 0 {
+                return String::from("/");
+            }
+            return String::from(&request_path[..i]);
+        }
+    }
+    String::from("/")
+}
+
+/// Parse one `Set-Cookie` value received for `request_host`/`request_path`.
+/// Cookies for a foreign domain and `Secure` cookies over plain HTTP are
+/// rejected.
+pub fn http_parse_set_cookie(
+    value: &str,
+    request_host: &str,
+    request_path: &str,
+    is_https: bool,
+    now_ticks: u64,
+) -> Option<HttpCookieEntry> {
+    let mut parts = value.split(';');
+    let first = parts.next()?.trim();
+    let (name_raw, value_raw) = first.split_once('=')?;
+    let name = name_raw.trim();
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut cookie = HttpCookieEntry {
+        name: String::from(name),
+        value: String::from(value_raw.trim()),
+        domain: ascii_lowercase(request_host),
+        path: http_cookie_default_path(request_path),
+        secure: false,
+        host_only: true,
+        expires_at_ticks: None,
+    };
+
+    for attr in parts {
+        let token = attr.trim();
+        if token.is_empty() {
+            continue;
+        }
+        if token.eq_ignore_ascii_case("secure") {
+            cookie.secure = true;
+            continue;
+        }
+        if token.eq_ignore_ascii_case("httponly") {
+            continue;
+        }
+        if let Some((k, v)) = token.split_once('=') {
+            let key = ascii_lowercase(k.trim());
+            let val = v.trim();
+            match key.as_str() {
+                "domain" => {
+                    let normalized = val.trim_start_matches('.');
+                    if normalized.is_empty() {
+                        return None;
+                    }
+                    if !http_cookie_domain_matches(request_host, normalized, false) {
+                        return None;
+                    }
+                    cookie.domain = ascii_lowercase(normalized);
+                    cookie.host_only = false;
+                }
+                "path" if val.starts_with('/') => {
+                    cookie.path = String::from(val);
+                }
+                "max-age" => {
+                    if let Ok(seconds) = val.parse::<i64>() {
+                        if seconds <= 0 {
+                            cookie.expires_at_ticks = Some(now_ticks);
+                        } else {
+                            let ttl = (seconds as u64).saturating_mul(COOKIE_TICKS_PER_SECOND);
+                            cookie.expires_at_ticks = Some(now_ticks.saturating_add(ttl));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if cookie.secure && !is_https {
+        return None;
+    }
+    Some(cookie)
+}
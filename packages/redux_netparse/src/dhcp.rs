@@ -0,0 +1,223 @@
+//! DHCPv4 (RFC 2131 / 2132): the options of a server reply the socket does
+//! not decode itself, lease timers and the saved-lease record.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Offset of the magic cookie in a BOOTP message; options follow it.
+const DHCP_MAGIC_OFFSET: usize = 236;
+const DHCP_MAGIC: [u8; 4] = [99, 130, 83, 99];
+
+pub const DHCP_OPT_PAD: u8 = 0;
+pub const DHCP_OPT_SUBNET_MASK: u8 = 1;
+pub const DHCP_OPT_ROUTER: u8 = 3;
+pub const DHCP_OPT_DNS: u8 = 6;
+pub const DHCP_OPT_HOSTNAME: u8 = 12;
+pub const DHCP_OPT_DOMAIN: u8 = 15;
+pub const DHCP_OPT_LEASE_TIME: u8 = 51;
+pub const DHCP_OPT_MESSAGE_TYPE: u8 = 53;
+pub const DHCP_OPT_SERVER_ID: u8 = 54;
+pub const DHCP_OPT_RENEWAL_TIME: u8 = 58;
+pub const DHCP_OPT_REBINDING_TIME: u8 = 59;
+pub const DHCP_OPT_END: u8 = 255;
+/// Longest host name label sent in option 12.
+pub const DHCP_HOSTNAME_MAX: usize = 63;
+
+/// Options of a DHCPACK the lease bookkeeping needs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DhcpAckOptions {
+    pub message_type: Option<u8>,
+    pub lease_s: Option<u32>,
+    pub renew_s: Option<u32>,
+    pub rebind_s: Option<u32>,
+    pub server_id: Option<[u8; 4]>,
+    pub domain: Option<String>,
+}
+
+fn be_u32(data: &[u8]) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(..4)?.try_into().ok()?))
+}
+
+/// Domain names are printable ASCII without spaces; servers pad option 15
+/// with NULs now and then.
+fn parse_domain(data: &[u8]) -> Option<String> {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    let name = &data[..end];
+    let name = name.strip_suffix(b".").unwrap_or(name);
+    if name.is_empty() || !name.iter().all(|&b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.') {
+        return None;
+    }
+    Some(name.iter().map(|&b| b.to_ascii_lowercase() as char).collect())
+}
+
+/// Parse the options of a raw BOOTP/DHCP message (the UDP payload). `None`
+/// when the message is too short, lacks the magic cookie or an option runs
+/// past the end.
+pub fn parse_dhcp_ack_options(message: &[u8]) -> Option<DhcpAckOptions> {
+    if message.get(DHCP_MAGIC_OFFSET..DHCP_MAGIC_OFFSET + 4)? != DHCP_MAGIC {
+        return None;
+    }
+    let mut out = DhcpAckOptions::default();
+    let mut rest = &message[DHCP_MAGIC_OFFSET + 4..];
+    while let Some((&kind, tail)) = rest.split_first() {
+        match kind {
+            DHCP_OPT_PAD => {
+                rest = tail;
+                continue;
+            }
+            DHCP_OPT_END => break,
+            _ => {}
+        }
+        let (&len, tail) = tail.split_first()?;
+        let data = tail.get(..len as usize)?;
+        rest = &tail[len as usize..];
+        match kind {
+            DHCP_OPT_MESSAGE_TYPE => out.message_type = data.first().copied(),
+            DHCP_OPT_LEASE_TIME => out.lease_s = be_u32(data),
+            DHCP_OPT_RENEWAL_TIME => out.renew_s = be_u32(data),
+            DHCP_OPT_REBINDING_TIME => out.rebind_s = be_u32(data),
+            DHCP_OPT_SERVER_ID => out.server_id = data.get(..4).and_then(|ip| ip.try_into().ok()),
+            DHCP_OPT_DOMAIN => out.domain = parse_domain(data),
+            _ => {}
+        }
+    }
+    Some(out)
+}
+
+/// Renewal (T1) and rebinding (T2) times in seconds for a lease of
+/// `lease_s`: the server's options 58/59 when they are consistent, else the
+/// RFC 2131 defaults of 0.5 and 0.875 of the lease. Always
+/// `t1 <= t2 <= lease_s`.
+pub fn dhcp_lease_timers(lease_s: u32, renew_s: Option<u32>, rebind_s: Option<u32>) -> (u32, u32) {
+    let t2 = match rebind_s {
+        Some(t2) if t2 <= lease_s => t2,
+        _ => (lease_s as u64 * 7 / 8) as u32,
+    };
+    let t1 = match renew_s {
+        Some(t1) if t1 <= t2 => t1,
+        _ => (lease_s / 2).min(t2),
+    };
+    (t1, t2)
+}
+
+/// Host name for option 12: one DNS label (RFC 1123), lowercase, other
+/// characters dropped. `None` when nothing usable is left.
+pub fn dhcp_hostname(name: &str) -> Option<String> {
+    let label: String = name
+        .split('.')
+        .next()
+        .unwrap_or("")
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .map(|c| c.to_ascii_lowercase())
+        .take(DHCP_HOSTNAME_MAX)
+        .collect();
+    let label = label.trim_matches('-');
+    if label.is_empty() {
+        None
+    } else {
+        Some(String::from(label))
+    }
+}
+
+/// A lease as saved between boots.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DhcpLeaseRecord {
+    pub address: [u8; 4],
+    pub prefix_len: u8,
+    pub router: Option<[u8; 4]>,
+    pub dns: Vec<[u8; 4]>,
+    pub server: Option<[u8; 4]>,
+    pub domain: Option<String>,
+    /// Wall-clock second the lease was granted or last renewed.
+    pub acquired_unix_s: i64,
+    pub lease_s: u32,
+}
+
+fn ip_text(ip: &[u8; 4]) -> String {
+    alloc::format!("{}.{}.{}.{}", ip[0], ip[1], ip[2], ip[3])
+}
+
+fn parse_ip(text: &str) -> Option<[u8; 4]> {
+    let mut out = [0u8; 4];
+    let mut parts = text.split('.');
+    for byte in out.iter_mut() {
+        let part = parts.next()?;
+        if part.is_empty() || part.len() > 3 || !part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        *byte = part.parse().ok()?;
+    }
+    parts.next().is_none().then_some(out)
+}
+
+fn parse_optional_ip(text: &str) -> Option<Option<[u8; 4]>> {
+    if text.is_empty() {
+        Some(None)
+    } else {
+        parse_ip(text).map(Some)
+    }
+}
+
+impl DhcpLeaseRecord {
+    /// `addr/prefix;router;dns,dns;server;acquired;lease;domain`, empty
+    /// fields for what the server did not send.
+    pub fn to_text(&self) -> String {
+        let dns: Vec<String> = self.dns.iter().map(ip_text).collect();
+        alloc::format!(
+            "{}/{};{};{};{};{};{};{}",
+            ip_text(&self.address),
+            self.prefix_len,
+            self.router.as_ref().map(ip_text).unwrap_or_default(),
+            dns.join(","),
+            self.server.as_ref().map(ip_text).unwrap_or_default(),
+            self.acquired_unix_s,
+            self.lease_s,
+            self.domain.as_deref().unwrap_or("")
+        )
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut fields = text.split(';');
+        let (address, prefix) = fields.next()?.split_once('/')?;
+        let prefix_len: u8 = prefix.parse().ok()?;
+        if prefix_len == 0 || prefix_len > 32 {
+            return None;
+        }
+        let router = parse_optional_ip(fields.next()?)?;
+        let dns_field = fields.next()?;
+        let mut dns = Vec::new();
+        if !dns_field.is_empty() {
+            for ip in dns_field.split(',') {
+                dns.push(parse_ip(ip)?);
+            }
+        }
+        let server = parse_optional_ip(fields.next()?)?;
+        let acquired_unix_s = fields.next()?.parse().ok()?;
+        let lease_s = fields.next()?.parse().ok()?;
+        let domain = match fields.next()? {
+            "" => None,
+            name => Some(parse_domain(name.as_bytes())?),
+        };
+        if fields.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            address: parse_ip(address)?,
+            prefix_len,
+            router,
+            dns,
+            server,
+            domain,
+            acquired_unix_s,
+            lease_s,
+        })
+    }
+
+    /// Seconds of lease left at `now_unix_s`; 0 once expired.
+    pub fn remaining_s(&self, now_unix_s: i64) -> u64 {
+        let expires = self.acquired_unix_s.saturating_add(self.lease_s as i64);
+        // A clock that went backwards must not stretch the lease.
+        expires.saturating_sub(now_unix_s).clamp(0, self.lease_s as i64) as u64
+    }
+}
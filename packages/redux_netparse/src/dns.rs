@@ -0,0 +1,228 @@
+//! DNS messages (RFC 1035) as multicast DNS uses them (RFC 6762): queries
+//! for service discovery (RFC 6763) and the A, PTR, SRV and TXT records of
+//! the answers, with name compression.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub const DNS_TYPE_A: u16 = 1;
+pub const DNS_TYPE_PTR: u16 = 12;
+pub const DNS_TYPE_TXT: u16 = 16;
+pub const DNS_TYPE_SRV: u16 = 33;
+pub const DNS_TYPE_ANY: u16 = 255;
+pub const DNS_CLASS_IN: u16 = 1;
+/// Top bit of the question class: ask for a unicast reply (mDNS "QU").
+pub const MDNS_UNICAST_RESPONSE: u16 = 0x8000;
+pub const MDNS_PORT: u16 = 5353;
+pub const MDNS_GROUP: [u8; 4] = [224, 0, 0, 251];
+/// Longest name in presentation form.
+pub const DNS_NAME_MAX: usize = 253;
+const DNS_HEADER_LEN: usize = 12;
+const DNS_FLAG_RESPONSE: u16 = 0x8000;
+/// Compression pointers followed for one name before giving up on a loop.
+const DNS_MAX_POINTERS: usize = 32;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DnsData {
+    A([u8; 4]),
+    Ptr(String),
+    Srv { priority: u16, weight: u16, port: u16, target: String },
+    /// `key=value` strings in wire order.
+    Txt(Vec<String>),
+    Other,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DnsRecord {
+    /// Without the trailing dot; compare names ignoring ASCII case.
+    pub name: String,
+    pub rtype: u16,
+    pub ttl: u32,
+    pub data: DnsData,
+}
+
+/// A parsed message. `records` holds the answer, authority and additional
+/// sections in that order; mDNS responders put SRV/TXT/A in "additional".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DnsMessage {
+    pub id: u16,
+    pub is_response: bool,
+    pub records: Vec<DnsRecord>,
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Append `name` in wire form. `None` for empty labels or labels over 63 bytes.
+fn push_name(out: &mut Vec<u8>, name: &str) -> Option<()> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.len() > DNS_NAME_MAX {
+        return None;
+    }
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return None;
+        }
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+    Some(())
+}
+
+/// Query with one question per `(name, type)`; `unicast_response` sets the
+/// mDNS QU bit on each.
+pub fn build_dns_query(id: u16, questions: &[(&str, u16)], unicast_response: bool) -> Option<Vec<u8>> {
+    if questions.is_empty() || questions.len() > u16::MAX as usize {
+        return None;
+    }
+    let mut out = Vec::with_capacity(DNS_HEADER_LEN + questions.len() * 32);
+    out.extend_from_slice(&id.to_be_bytes());
+    out.extend_from_slice(&[0, 0]);
+    out.extend_from_slice(&(questions.len() as u16).to_be_bytes());
+    out.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+    let class = DNS_CLASS_IN | if unicast_response { MDNS_UNICAST_RESPONSE } else { 0 };
+    for (name, qtype) in questions {
+        push_name(&mut out, name)?;
+        out.extend_from_slice(&qtype.to_be_bytes());
+        out.extend_from_slice(&class.to_be_bytes());
+    }
+    Some(out)
+}
+
+/// Read the (possibly compressed) name at `at`. Returns the name and the
+/// offset just past it in the original position.
+fn read_name(msg: &[u8], at: usize) -> Option<(String, usize)> {
+    let mut name = String::new();
+    let mut pos = at;
+    let mut end = None;
+    let mut pointers = 0usize;
+    loop {
+        let len = *msg.get(pos)? as usize;
+        match len & 0xC0 {
+            0x00 if len == 0 => {
+                pos += 1;
+                break;
+            }
+            0x00 => {
+                let label = msg.get(pos + 1..pos + 1 + len)?;
+                if !name.is_empty() {
+                    name.push('.');
+                }
+                // Instance names are UTF-8 (RFC 6763 4.3); keep what decodes.
+                name.push_str(&String::from_utf8_lossy(label));
+                if name.len() > DNS_NAME_MAX * 2 {
+                    return None;
+                }
+                pos += 1 + len;
+            }
+            0xC0 => {
+                let target = (be_u16(msg, pos)? & 0x3FFF) as usize;
+                pointers += 1;
+                if pointers > DNS_MAX_POINTERS || target >= pos {
+                    return None;
+                }
+                end.get_or_insert(pos + 2);
+                pos = target;
+            }
+            _ => return None,
+        }
+    }
+    Some((name, end.unwrap_or(pos)))
+}
+
+fn parse_txt(data: &[u8]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut rest = data;
+    while let Some((&len, tail)) = rest.split_first() {
+        let Some(entry) = tail.get(..len as usize) else {
+            break;
+        };
+        if !entry.is_empty() {
+            out.push(String::from_utf8_lossy(entry).into_owned());
+        }
+        rest = &tail[len as usize..];
+    }
+    out
+}
+
+fn parse_record(msg: &[u8], at: usize) -> Option<(DnsRecord, usize)> {
+    let (name, pos) = read_name(msg, at)?;
+    let rtype = be_u16(msg, pos)?;
+    let ttl = be_u32(msg, pos + 4)?;
+    let rdlen = be_u16(msg, pos + 8)? as usize;
+    let rdata_at = pos + 10;
+    let rdata = msg.get(rdata_at..rdata_at + rdlen)?;
+    let data = match rtype {
+        DNS_TYPE_A if rdlen == 4 => DnsData::A(rdata.try_into().ok()?),
+        DNS_TYPE_PTR => DnsData::Ptr(read_name(msg, rdata_at)?.0),
+        DNS_TYPE_SRV if rdlen >= 7 => DnsData::Srv {
+            priority: be_u16(rdata, 0)?,
+            weight: be_u16(rdata, 2)?,
+            port: be_u16(rdata, 4)?,
+            target: read_name(msg, rdata_at + 6)?.0,
+        },
+        DNS_TYPE_TXT => DnsData::Txt(parse_txt(rdata)),
+        _ => DnsData::Other,
+    };
+    Some((DnsRecord { name, rtype, ttl, data }, rdata_at + rdlen))
+}
+
+/// Parse a whole message. `None` when the header or any question or record
+/// runs past the end, or a name is malformed.
+pub fn parse_dns_message(msg: &[u8]) -> Option<DnsMessage> {
+    if msg.len() < DNS_HEADER_LEN {
+        return None;
+    }
+    let id = be_u16(msg, 0)?;
+    let flags = be_u16(msg, 2)?;
+    let questions = be_u16(msg, 4)? as usize;
+    let total = be_u16(msg, 6)? as usize + be_u16(msg, 8)? as usize + be_u16(msg, 10)? as usize;
+    let mut pos = DNS_HEADER_LEN;
+    for _ in 0..questions {
+        pos = read_name(msg, pos)?.1 + 4;
+        if pos > msg.len() {
+            return None;
+        }
+    }
+    let mut records = Vec::with_capacity(total.min(64));
+    for _ in 0..total {
+        let (record, next) = parse_record(msg, pos)?;
+        records.push(record);
+        pos = next;
+    }
+    Some(DnsMessage {
+        id,
+        is_response: flags & DNS_FLAG_RESPONSE != 0,
+        records,
+    })
+}
+
+/// Value of `key` in TXT `key=value` strings; keys compare without case
+/// (RFC 6763 6.4). A bare `key` has the empty value.
+pub fn txt_value<'a>(entries: &'a [String], key: &str) -> Option<&'a str> {
+    entries.iter().find_map(|entry| match entry.split_once('=') {
+        Some((k, v)) if k.eq_ignore_ascii_case(key) => Some(v),
+        None if entry.eq_ignore_ascii_case(key) => Some(""),
+        _ => None,
+    })
+}
+
+/// The instance label of a service instance name: `"Oficina._ipp._tcp.local"`
+/// with service `"_ipp._tcp.local"` gives `"Oficina"`.
+pub fn instance_label<'a>(instance: &'a str, service: &str) -> &'a str {
+    let split = instance.len().saturating_sub(service.len() + 1);
+    match (instance.get(..split), instance.get(split..)) {
+        (Some(head), Some(tail))
+            if !head.is_empty() && tail.starts_with('.') && tail[1..].eq_ignore_ascii_case(service) =>
+        {
+            head
+        }
+        _ => instance,
+    }
+}
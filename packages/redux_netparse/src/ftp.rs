@@ -0,0 +1,315 @@
+//! FTP (RFC 959) control-channel replies, passive-mode addresses, `LIST`
+//! output and `ftp://` / `ftps://` targets.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::gopher::percent_decode;
+use crate::starts_with_ignore_ascii_case;
+
+pub const FTP_DEFAULT_PORT: u16 = 21;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FtpReply {
+    pub code: u16,
+    /// Reply text without the code; lines of a multi-line reply are joined
+    /// with `\n`.
+    pub text: String,
+    /// Bytes of the input this reply used, including the final CRLF.
+    pub consumed: usize,
+}
+
+impl FtpReply {
+    /// First digit: 1 preliminary, 2 done, 3 needs more, 4/5 failure.
+    pub fn class(&self) -> u16 {
+        self.code / 100
+    }
+}
+
+fn reply_code(line: &[u8]) -> Option<u16> {
+    if line.len() < 3 || !line[..3].iter().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let code = (line[0] - b'0') as u16 * 100 + (line[1] - b'0') as u16 * 10 + (line[2] - b'0') as u16;
+    if !(100..600).contains(&code) {
+        return None;
+    }
+    Some(code)
+}
+
+fn line_text(line: &[u8]) -> String {
+    String::from_utf8_lossy(line).trim_end_matches(['\r', '\n']).into()
+}
+
+/// Parse the first complete reply in `buf`. Returns `None` while the reply is
+/// still incomplete (or when `buf` does not start with a reply code).
+///
+/// A multi-line reply starts with `xyz-` and ends at the first line that
+/// begins with the same `xyz ` (RFC 959 section 4.2).
+pub fn parse_ftp_reply(buf: &[u8]) -> Option<FtpReply> {
+    let first_end = buf.iter().position(|&b| b == b'\n')?;
+    let first = &buf[..first_end];
+    let code = reply_code(first)?;
+    let first_text = line_text(first.get(4..).unwrap_or(&[]));
+
+    match first.get(3) {
+        Some(b'-') => {}
+        Some(b' ') | Some(b'\r') | None => {
+            return Some(FtpReply {
+                code,
+                text: first_text,
+                consumed: first_end + 1,
+            });
+        }
+        Some(_) => return None,
+    }
+
+    let mut text = first_text;
+    let mut pos = first_end + 1;
+    loop {
+        let rest = buf.get(pos..)?;
+        let end = rest.iter().position(|&b| b == b'\n')?;
+        let line = &rest[..end];
+        pos += end + 1;
+        let is_last = reply_code(line) == Some(code) && matches!(line.get(3), Some(b' ') | Some(b'\r') | None);
+        text.push('\n');
+        if is_last {
+            text.push_str(line_text(line.get(4..).unwrap_or(&[])).as_str());
+            return Some(FtpReply {
+                code,
+                text,
+                consumed: pos,
+            });
+        }
+        text.push_str(line_text(line).trim_start());
+    }
+}
+
+/// Address from a `227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)` reply.
+pub fn parse_pasv_reply(text: &str) -> Option<([u8; 4], u16)> {
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let mut nums = [0u8; 6];
+    let mut count = 0usize;
+    for part in text[start..].split(',') {
+        if count == 6 {
+            break;
+        }
+        let digits: &str = part
+            .trim_start()
+            .split(|c: char| !c.is_ascii_digit())
+            .next()
+            .unwrap_or("");
+        nums[count] = digits.parse::<u8>().ok()?;
+        count += 1;
+    }
+    if count != 6 {
+        return None;
+    }
+    let port = ((nums[4] as u16) << 8) | nums[5] as u16;
+    if port == 0 {
+        return None;
+    }
+    Some(([nums[0], nums[1], nums[2], nums[3]], port))
+}
+
+/// Port from a `229 Entering Extended Passive Mode (|||port|)` reply (RFC 2428).
+pub fn parse_epsv_reply(text: &str) -> Option<u16> {
+    let open = text.find('(')?;
+    let inner = text[open + 1..].split(')').next()?;
+    let delim = inner.chars().next()?;
+    if !(33..=126).contains(&(delim as u32)) || delim.is_ascii_digit() {
+        return None;
+    }
+    let fields: Vec<&str> = inner.split(delim).collect();
+    // "|||6446|" splits into ["", "", "", "6446", ""]; the reply carries no
+    // address, the data connection goes to the control connection's peer.
+    if fields.len() != 5 || !fields[1].is_empty() || !fields[2].is_empty() {
+        return None;
+    }
+    match fields[3].parse::<u16>() {
+        Ok(port) if port != 0 => Some(port),
+        _ => None,
+    }
+}
+
+/// Text of the first quoted path in a `257 "<path>"` reply (`""` is a literal quote).
+pub fn parse_pwd_reply(text: &str) -> Option<String> {
+    let start = text.find('"')? + 1;
+    let bytes = text.as_bytes();
+    let mut out = String::new();
+    let mut i = start;
+    while i < bytes.len() {
+        if bytes[i] == b'"' {
+            if bytes.get(i + 1) == Some(&b'"') {
+                out.push('"');
+                i += 2;
+                continue;
+            }
+            return Some(out);
+        }
+        let ch = text[i..].chars().next()?;
+        out.push(ch);
+        i += ch.len_utf8();
+    }
+    None
+}
+
+/// Command arguments go on the control connection verbatim: CR, LF and NUL
+/// would let a file name inject a second command.
+pub fn ftp_arg_is_safe(arg: &str) -> bool {
+    !arg.bytes().any(|b| b == b'\r' || b == b'\n' || b == 0)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FtpTarget {
+    /// `ftps://`: explicit TLS with `AUTH TLS` on the normal port.
+    pub secure: bool,
+    pub host: String,
+    pub port: u16,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    /// Initial directory, empty for the login directory.
+    pub path: String,
+}
+
+/// Parse `ftp://[user[:pass]@]host[:port][/path]`, the same with `ftps://`, or
+/// a bare `host[:port]`. User and password are percent-decoded.
+pub fn parse_ftp_target(text: &str) -> Option<FtpTarget> {
+    let text = text.trim();
+    let (secure, rest) = if starts_with_ignore_ascii_case(text, "ftps://") {
+        (true, &text[7..])
+    } else if starts_with_ignore_ascii_case(text, "ftp://") {
+        (false, &text[6..])
+    } else if text.contains("://") {
+        return None;
+    } else {
+        (false, text)
+    };
+
+    let authority_len = rest.find('/').unwrap_or(rest.len());
+    let authority = &rest[..authority_len];
+    let path = &rest[authority_len..];
+    let (userinfo, hostport) = match authority.rfind('@') {
+        Some(idx) => (Some(&authority[..idx]), &authority[idx + 1..]),
+        None => (None, authority),
+    };
+    let (host, port) = match hostport.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse::<u16>().ok()?),
+        None => (hostport, FTP_DEFAULT_PORT),
+    };
+    if host.is_empty() || host.bytes().any(|b| b <= b' ' || b == b'/' || b == b'@') {
+        return None;
+    }
+
+    let (user, password) = match userinfo {
+        Some(info) => match info.split_once(':') {
+            Some((user, pass)) => (Some(percent_decode(user)), Some(percent_decode(pass))),
+            None => (Some(percent_decode(info)), None),
+        },
+        None => (None, None),
+    };
+    let path = percent_decode(path);
+    let creds_ok = user.as_deref().map(ftp_arg_is_safe).unwrap_or(true)
+        && password.as_deref().map(ftp_arg_is_safe).unwrap_or(true);
+    if !creds_ok || !ftp_arg_is_safe(path.as_str()) {
+        return None;
+    }
+
+    Some(FtpTarget {
+        secure,
+        host: String::from(host),
+        port,
+        user: user.filter(|u| !u.is_empty()),
+        password,
+        path: if path == "/" { String::new() } else { path },
+    })
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FtpListEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub is_link: bool,
+    pub size: Option<u64>,
+}
+
+fn parse_unix_list_line(line: &str) -> Option<FtpListEntry> {
+    // drwxr-xr-x  2 user group  4096 Jan  1 12:00 name with spaces
+    let kind = line.as_bytes()[0];
+    if !matches!(kind, b'-' | b'd' | b'l' | b'b' | b'c' | b'p' | b's') {
+        return None;
+    }
+    let mut fields = Vec::with_capacity(9);
+    let mut rest = line;
+    for _ in 0..8 {
+        rest = rest.trim_start();
+        let end = rest.find(' ')?;
+        fields.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    let name = rest.trim_start();
+    if fields[0].len() < 10 || name.is_empty() {
+        return None;
+    }
+    let size = fields[4].parse::<u64>().ok();
+    let (name, is_link) = if kind == b'l' {
+        (name.split(" -> ").next().unwrap_or(name), true)
+    } else {
+        (name, false)
+    };
+    Some(FtpListEntry {
+        name: String::from(name),
+        is_dir: kind == b'd',
+        is_link,
+        size: if kind == b'-' { size } else { None },
+    })
+}
+
+fn parse_dos_list_line(line: &str) -> Option<FtpListEntry> {
+    // 01-02-23  10:00AM       <DIR>          name
+    // 01-02-23  10:00AM                 1234 name
+    let mut rest = line;
+    let mut fields = Vec::with_capacity(3);
+    for _ in 0..3 {
+        rest = rest.trim_start();
+        let end = rest.find(' ')?;
+        fields.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    let date_ok = fields[0].len() >= 8 && fields[0].bytes().all(|b| b.is_ascii_digit() || b == b'-' || b == b'/');
+    if !date_ok {
+        return None;
+    }
+    let name = rest.trim_start();
+    if name.is_empty() {
+        return None;
+    }
+    let is_dir = fields[2].eq_ignore_ascii_case("<DIR>");
+    let size = if is_dir { None } else { Some(fields[2].parse::<u64>().ok()?) };
+    Some(FtpListEntry {
+        name: String::from(name),
+        is_dir,
+        is_link: false,
+        size,
+    })
+}
+
+/// One line of `LIST` output (Unix `ls -l` or IIS/DOS style). Totals and
+/// unrecognised lines give `None`.
+pub fn parse_list_line(line: &str) -> Option<FtpListEntry> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    if line.is_empty() || starts_with_ignore_ascii_case(line, "total ") {
+        return None;
+    }
+    let entry = parse_unix_list_line(line).or_else(|| parse_dos_list_line(line))?;
+    if entry.name == "." || entry.name == ".." {
+        return None;
+    }
+    Some(entry)
+}
+
+/// Parse a whole `LIST` response. Unrecognised lines are dropped; callers
+/// that want them can fall back to the raw text.
+pub fn parse_list(text: &str) -> Vec<FtpListEntry> {
+    text.split('\n').filter_map(parse_list_line).collect()
+}
@@ -0,0 +1,178 @@
+//! Gemini protocol: request URLs, the `<status> <meta>` response header and
+//! `text/gemini` documents.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::starts_with_ignore_ascii_case;
+
+pub const GEMINI_DEFAULT_PORT: u16 = 1965;
+/// Longest request URL the protocol allows.
+pub const GEMINI_MAX_URL: usize = 1024;
+/// Longest `<meta>` the protocol allows.
+pub const GEMINI_MAX_META: usize = 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeminiUrl {
+    pub host: String,
+    pub port: u16,
+    /// Path and query, always starting with `/`.
+    pub path: String,
+}
+
+/// Split a `gemini://` URL. The request line is the whole URL, so the caller
+/// sends `url` itself; this only provides the host and port to connect to.
+pub fn parse_gemini_url(url: &str) -> Option<GeminiUrl> {
+    if !starts_with_ignore_ascii_case(url, "gemini://") || url.len() > GEMINI_MAX_URL {
+        return None;
+    }
+    let rest = &url[9..];
+    let authority_len = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let authority = &rest[..authority_len];
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse::<u16>().ok()?),
+        None => (authority, GEMINI_DEFAULT_PORT),
+    };
+    if host.is_empty() || host.bytes().any(|b| b <= b' ' || b == b'@') {
+        return None;
+    }
+    let tail = &rest[authority_len..];
+    let tail = match tail.find('#') {
+        Some(idx) => &tail[..idx],
+        None => tail,
+    };
+    let path = if tail.starts_with('/') {
+        String::from(tail)
+    } else {
+        alloc::format!("/{}", tail)
+    };
+    Some(GeminiUrl {
+        host: String::from(host),
+        port,
+        path,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeminiHeader {
+    /// Two-digit status; the first digit is the class (1 input, 2 success,
+    /// 3 redirect, 4/5 failure, 6 client certificate).
+    pub status: u8,
+    pub meta: String,
+    pub body_offset: usize,
+}
+
+impl GeminiHeader {
+    pub fn class(&self) -> u8 {
+        self.status / 10
+    }
+}
+
+/// Parse the response header line. `None` until the full `\r\n`-terminated
+/// line is present, or when it is malformed.
+pub fn parse_gemini_header(raw: &[u8]) -> Option<GeminiHeader> {
+    let line_end = raw.iter().take(GEMINI_MAX_META + 4).position(|&b| b == b'\n')?;
+    let line = &raw[..line_end];
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    if line.len() < 2 || !line[0].is_ascii_digit() || !line[1].is_ascii_digit() || line[0] == b'0' {
+        return None;
+    }
+    let status = (line[0] - b'0') * 10 + (line[1] - b'0');
+    let meta = match line.get(2) {
+        None => "",
+        Some(b' ') | Some(b'\t') => core::str::from_utf8(&line[3..]).ok()?,
+        Some(_) => return None,
+    };
+    if meta.len() > GEMINI_MAX_META {
+        return None;
+    }
+    Some(GeminiHeader {
+        status,
+        meta: String::from(meta.trim()),
+        body_offset: line_end + 1,
+    })
+}
+
+/// MIME type and `charset` of a 2x `<meta>`; an empty meta means
+/// `text/gemini; charset=utf-8`.
+pub fn gemini_mime(meta: &str) -> (String, Option<String>) {
+    let meta = meta.trim();
+    if meta.is_empty() {
+        return (String::from("text/gemini"), None);
+    }
+    let mut parts = meta.split(';');
+    let mime = crate::ascii_lowercase(parts.next().unwrap_or("").trim());
+    let mut charset = None;
+    for param in parts {
+        if let Some((key, value)) = param.split_once('=') {
+            if key.trim().eq_ignore_ascii_case("charset") {
+                charset = Some(crate::ascii_lowercase(value.trim().trim_matches('"')));
+            }
+        }
+    }
+    (mime, charset)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GemLine {
+    Text(String),
+    /// `=> url [label]`; `url` is as written in the document.
+    Link { url: String, label: String },
+    Heading { level: u8, text: String },
+    ListItem(String),
+    Quote(String),
+    /// One line inside a ```` ``` ```` block, kept verbatim.
+    Preformatted(String),
+    /// Opening fence with its alt text; the closing fence is not reported.
+    PreformatStart(String),
+}
+
+/// Line-oriented `text/gemini` parser.
+pub fn parse_gemtext(text: &str) -> Vec<GemLine> {
+    let mut out = Vec::new();
+    let mut preformatted = false;
+    for raw_line in text.split('\n') {
+        let line = raw_line.strip_suffix('\r').unwrap_or(raw_line);
+        if let Some(alt) = line.strip_prefix("```") {
+            if !preformatted {
+                out.push(GemLine::PreformatStart(String::from(alt.trim())));
+            }
+            preformatted = !preformatted;
+            continue;
+        }
+        if preformatted {
+            out.push(GemLine::Preformatted(String::from(line)));
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("=>") {
+            let rest = rest.trim_start();
+            let url_end = rest.find([' ', '\t']).unwrap_or(rest.len());
+            let url = &rest[..url_end];
+            if url.is_empty() {
+                out.push(GemLine::Text(String::from(line)));
+            } else {
+                out.push(GemLine::Link {
+                    url: String::from(url),
+                    label: String::from(rest[url_end..].trim()),
+                });
+            }
+        } else if line.starts_with('#') {
+            let level = line.bytes().take(3).take_while(|&b| b == b'#').count();
+            out.push(GemLine::Heading {
+                level: level as u8,
+                text: String::from(line[level..].trim()),
+            });
+        } else if let Some(item) = line.strip_prefix("* ") {
+            out.push(GemLine::ListItem(String::from(item.trim())));
+        } else if let Some(quote) = line.strip_prefix('>') {
+            out.push(GemLine::Quote(String::from(quote.trim())));
+        } else {
+            out.push(GemLine::Text(String::from(line)));
+        }
+    }
+    // A document ending in "\n" yields one trailing empty line.
+    if matches!(out.last(), Some(GemLine::Text(t)) if t.is_empty()) && text.ends_with('\n') {
+        out.pop();
+    }
+    out
+}
@@ -0,0 +1,215 @@
+//! Gopher (RFC 1436): `gopher://` URLs (RFC 4266), menus and text items.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::starts_with_ignore_ascii_case;
+
+pub const GOPHER_DEFAULT_PORT: u16 = 70;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GopherUrl {
+    pub host: String,
+    pub port: u16,
+    /// Item type character; `1` (menu) when the URL has no path.
+    pub item_type: u8,
+    pub selector: String,
+    /// Search string for type `7` items (`%09` in the URL).
+    pub query: Option<String>,
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0usize;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(hi), Some(lo)) = (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                out.push(hi << 4 | lo);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Percent-encode the bytes a selector may contain but a URL may not.
+pub fn percent_encode_selector(selector: &str) -> String {
+    let mut out = String::with_capacity(selector.len());
+    for b in selector.bytes() {
+        if b <= b' ' || b >= 0x7F || matches!(b, b'%' | b'?' | b'#' | b'"' | b'<' | b'>') {
+            out.push_str(alloc::format!("%{:02X}", b).as_str());
+        } else {
+            out.push(b as char);
+        }
+    }
+    out
+}
+
+pub fn parse_gopher_url(url: &str) -> Option<GopherUrl> {
+    if !starts_with_ignore_ascii_case(url, "gopher://") {
+        return None;
+    }
+    let rest = &url[9..];
+    let authority_len = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let authority = &rest[..authority_len];
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse::<u16>().ok()?),
+        None => (authority, GOPHER_DEFAULT_PORT),
+    };
+    if host.is_empty() || host.bytes().any(|b| b <= b' ') {
+        return None;
+    }
+
+    let tail = &rest[authority_len..];
+    let tail = match tail.find('#') {
+        Some(idx) => &tail[..idx],
+        None => tail,
+    };
+    // "gopher://host/?query" is a common shorthand for a search at "/".
+    let (path, url_query) = match tail.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (tail, None),
+    };
+    let path = path.strip_prefix('/').unwrap_or(path);
+    let decoded = percent_decode(path);
+    let item_type = decoded.bytes().next().unwrap_or(b'1');
+    if !item_type.is_ascii() {
+        return None;
+    }
+    let after_type = decoded.get(1..).unwrap_or("");
+    let (selector, tab_query) = match after_type.split_once('\t') {
+        Some((selector, query)) => (selector, Some(query)),
+        None => (after_type, None),
+    };
+    let query = tab_query
+        .map(String::from)
+        .or_else(|| url_query.map(percent_decode))
+        .filter(|q| !q.is_empty());
+    if selector.contains(['\r', '\n']) || query.as_deref().map(|q| q.contains(['\r', '\n'])).unwrap_or(false) {
+        return None;
+    }
+    Some(GopherUrl {
+        host: String::from(host),
+        port,
+        item_type,
+        selector: String::from(selector),
+        query,
+    })
+}
+
+/// The line sent to the server for `url`.
+pub fn gopher_request_line(url: &GopherUrl) -> String {
+    match url.query.as_ref() {
+        Some(query) => alloc::format!("{}\t{}\r\n", url.selector, query),
+        None => alloc::format!("{}\r\n", url.selector),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GopherItem {
+    pub item_type: u8,
+    pub display: String,
+    pub selector: String,
+    pub host: String,
+    pub port: u16,
+}
+
+impl GopherItem {
+    /// Informational (`i`) and error (`3`) lines are not links.
+    pub fn is_link(&self) -> bool {
+        !matches!(self.item_type, b'i' | b'3')
+    }
+
+    /// Absolute URL the item points to: `h` items with a `URL:` selector
+    /// give the embedded URL, everything else a `gopher://` URL.
+    pub fn url(&self) -> Option<String> {
+        if !self.is_link() || self.host.is_empty() {
+            return None;
+        }
+        if self.item_type == b'h' {
+            if let Some(target) = self.selector.strip_prefix("URL:") {
+                return Some(String::from(target));
+            }
+        }
+        let port = if self.port == GOPHER_DEFAULT_PORT {
+            String::new()
+        } else {
+            alloc::format!(":{}", self.port)
+        };
+        Some(alloc::format!(
+            "gopher://{}{}/{}{}",
+            self.host,
+            port,
+            self.item_type as char,
+            percent_encode_selector(self.selector.as_str())
+        ))
+    }
+}
+
+/// Strip the `.` terminator line and undo dot-stuffing of a text item.
+pub fn gopher_text_body(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for raw_line in text.split('\n') {
+        let line = raw_line.strip_suffix('\r').unwrap_or(raw_line);
+        if line == "." {
+            break;
+        }
+        out.push_str(line.strip_prefix("..").map(|_| &line[1..]).unwrap_or(line));
+        out.push('\n');
+    }
+    while out.ends_with("\n\n") {
+        out.pop();
+    }
+    out
+}
+
+/// Parse a menu (type `1` / `7` response). Malformed lines become `i` items
+/// so the text is not lost.
+pub fn parse_gopher_menu(text: &str) -> Vec<GopherItem> {
+    let mut items = Vec::new();
+    for raw_line in text.split('\n') {
+        let line = raw_line.strip_suffix('\r').unwrap_or(raw_line);
+        if line == "." {
+            break;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let item_type = line.as_bytes()[0];
+        let mut fields = line.get(1..).unwrap_or("").split('\t');
+        let display = fields.next().unwrap_or("");
+        let selector = fields.next();
+        let host = fields.next();
+        let port = fields.next().and_then(|p| p.trim().parse::<u16>().ok());
+        match (selector, host, port) {
+            (Some(selector), Some(host), Some(port)) if item_type.is_ascii_graphic() => items.push(GopherItem {
+                item_type,
+                display: String::from(display),
+                selector: String::from(selector),
+                host: String::from(host.trim()),
+                port,
+            }),
+            _ => items.push(GopherItem {
+                item_type: b'i',
+                display: String::from(line),
+                selector: String::new(),
+                host: String::new(),
+                port: 0,
+            }),
+        }
+    }
+    items
+}
@@ -0,0 +1,880 @@
+//! HPACK (RFC 7541) header compression for the HTTP/2 client.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub const HPACK_DYNAMIC_TABLE_DEFAULT_MAX: usize = 4096;
+pub const HPACK_DYNAMIC_TABLE_ABSOLUTE_MAX: usize = 64 * 1024;
+
+#[derive(Clone)]
+struct HpackDynamicEntry {
+    name: String,
+    value: String,
+    size: usize,
+}
+
+#[derive(Clone)]
+pub struct HpackDynamicTable {
+    entries: Vec<HpackDynamicEntry>,
+    size: usize,
+    max_size: usize,
+}
+
+impl HpackDynamicTable {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            size: 0,
+            max_size: HPACK_DYNAMIC_TABLE_DEFAULT_MAX,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Current size in the RFC 7541 sense (name + value + 32 per entry).
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    pub fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = core::cmp::min(max_size, HPACK_DYNAMIC_TABLE_ABSOLUTE_MAX);
+        self.evict_to_fit();
+    }
+
+    fn evict_to_fit(&mut self) {
+        while self.size > self.max_size {
+            if let Some(last) = self.entries.pop() {
+                self.size = self.size.saturating_sub(last.size);
+            } else {
+                self.size = 0;
+                break;
+            }
+        }
+    }
+
+    pub fn insert(&mut self, name: String, value: String) {
+        let entry_size = name.len().saturating_add(value.len()).saturating_add(32);
+        if entry_size > self.max_size {
+            self.entries.clear();
+            self.size = 0;
+            return;
+        }
+
+        while self.size.saturating_add(entry_size) > self.max_size {
+            if let Some(last) = self.entries.pop() {
+                self.size = self.size.saturating_sub(last.size);
+            } else {
+                self.size = 0;
+                break;
+            }
+        }
+
+        self.entries.insert(
+            0,
+            HpackDynamicEntry {
+                name,
+                value,
+                size: entry_size,
+            },
+        );
+        self.size = self.size.saturating_add(entry_size);
+    }
+
+    pub fn get(&self, absolute_index: u32) -> Option<(&str, &str)> {
+        if absolute_index <= 61 {
+            return None;
+        }
+        let dyn_index = (absolute_index as usize).checked_sub(62)?;
+        let entry = self.entries.get(dyn_index)?;
+        Some((entry.name.as_str(), entry.value.as_str()))
+    }
+}
+
+impl Default for HpackDynamicTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn hpack_encode_prefixed_integer(out: &mut Vec<u8>, first_prefix: u8, prefix_bits: u8, value: u32) {
+    let max_prefix = (1u32 << prefix_bits) - 1;
+    if value < max_prefix {
+        out.push(first_prefix | value as u8);
+        return;
+    }
+
+    out.push(first_prefix | max_prefix as u8);
+    let mut remaining = value - max_prefix;
+    while remaining >= 128 {
+        out.push(((remaining as u8) & 0x7F) | 0x80);
+        remaining >>= 7;
+    }
+    out.push(remaining as u8);
+}
+
+pub fn hpack_encode_string_no_huffman(out: &mut Vec<u8>, value: &str) {
+    let bytes = value.as_bytes();
+    hpack_encode_prefixed_integer(out, 0x00, 7, bytes.len() as u32);
+    out.extend_from_slice(bytes);
+}
+
+pub fn hpack_encode_string_huffman(out: &mut Vec<u8>, value: &str) {
+    let mut encoded = Vec::new();
+    let mut bit_buffer: u64 = 0;
+    let mut bit_count: u32 = 0;
+
+    for &byte in value.as_bytes().iter() {
+        let (nbits, code) = HPACK_HUFFMAN_CODES[byte as usize];
+        bit_buffer = (bit_buffer << nbits) | code as u64;
+        bit_count = bit_count.saturating_add(nbits as u32);
+
+        while bit_count >= 8 {
+            let shift = bit_count - 8;
+            encoded.push(((bit_buffer >> shift) & 0xFF) as u8);
+            bit_count -= 8;
+            if bit_count == 0 {
+                bit_buffer = 0;
+            } else {
+                bit_buffer &= (1u64 << bit_count) - 1;
+            }
+        }
+    }
+
+    if bit_count > 0 {
+        let pad_bits = 8 - bit_count;
+        let padded = (bit_buffer << pad_bits) | ((1u64 << pad_bits) - 1);
+        encoded.push((padded & 0xFF) as u8);
+    }
+
+    hpack_encode_prefixed_integer(out, 0x80, 7, encoded.len() as u32);
+    out.extend_from_slice(encoded.as_slice());
+}
+
+pub fn hpack_encode_string(out: &mut Vec<u8>, value: &str, use_huffman: bool) {
+    if use_huffman {
+        hpack_encode_string_huffman(out, value);
+    } else {
+        hpack_encode_string_no_huffman(out, value);
+    }
+}
+
+pub fn hpack_encode_indexed_header(out: &mut Vec<u8>, index: u32) {
+    hpack_encode_prefixed_integer(out, 0x80, 7, index);
+}
+
+fn hpack_find_header_exact_index(
+    dynamic: &HpackDynamicTable,
+    name: &str,
+    value: &str,
+) -> Option<u32> {
+    for idx in 1..=61u32 {
+        if let Some((n, v)) = hpack_static_table(idx) {
+            if n == name && v == value {
+                return Some(idx);
+            }
+        }
+    }
+    for (i, entry) in dynamic.entries.iter().enumerate() {
+        if entry.name == name && entry.value == value {
+            return Some(62u32.saturating_add(i as u32));
+        }
+    }
+    None
+}
+
+fn hpack_find_header_name_index(dynamic: &HpackDynamicTable, name: &str) -> Option<u32> {
+    for idx in 1..=61u32 {
+        if let Some((n, _)) = hpack_static_table(idx) {
+            if n == name {
+                return Some(idx);
+            }
+        }
+    }
+    for (i, entry) in dynamic.entries.iter().enumerate() {
+        if entry.name == name {
+            return Some(62u32.saturating_add(i as u32));
+        }
+    }
+    None
+}
+
+pub fn hpack_encode_literal_header(
+    out: &mut Vec<u8>,
+    dynamic: &mut HpackDynamicTable,
+    name: &str,
+    value: &str,
+    incremental_indexing: bool,
+    use_huffman: bool,
+) {
+    let name_index = hpack_find_header_name_index(dynamic, name).unwrap_or(0);
+    if incremental_indexing {
+        hpack_encode_prefixed_integer(out, 0x40, 6, name_index);
+        if name_index == 0 {
+            hpack_encode_string(out, name, use_huffman);
+        }
+        hpack_encode_string(out, value, use_huffman);
+        dynamic.insert(String::from(name), String::from(value));
+    } else {
+        hpack_encode_prefixed_integer(out, 0x00, 4, name_index);
+        if name_index == 0 {
+            hpack_encode_string(out, name, use_huffman);
+        }
+        hpack_encode_string(out, value, use_huffman);
+    }
+}
+
+fn hpack_should_index_request_header(name: &str) -> bool {
+    matches!(
+        name,
+        ":authority" | "accept" | "accept-language" | "accept-encoding" | "user-agent"
+    )
+}
+
+/// Encode one request header, reusing the static or dynamic table when the
+/// exact pair is already there.
+pub fn hpack_encode_request_header(
+    out: &mut Vec<u8>,
+    dynamic: &mut HpackDynamicTable,
+    name: &str,
+    value: &str,
+    use_huffman: bool,
+) {
+    if let Some(index) = hpack_find_header_exact_index(dynamic, name, value) {
+        hpack_encode_indexed_header(out, index);
+        return;
+    }
+    hpack_encode_literal_header(
+        out,
+        dynamic,
+        name,
+        value,
+        hpack_should_index_request_header(name),
+        use_huffman,
+    );
+}
+
+pub const HPACK_HUFFMAN_CODES: [(u8, u32); 257] = [
+    (13, 0x1ff8),
+    (23, 0x7fffd8),
+    (28, 0xfffffe2),
+    (28, 0xfffffe3),
+    (28, 0xfffffe4),
+    (28, 0xfffffe5),
+    (28, 0xfffffe6),
+    (28, 0xfffffe7),
+    (28, 0xfffffe8),
+    (24, 0xffffea),
+    (30, 0x3ffffffc),
+    (28, 0xfffffe9),
+    (28, 0xfffffea),
+    (30, 0x3ffffffd),
+    (28, 0xfffffeb),
+    (28, 0xfffffec),
+    (28, 0xfffffed),
+    (28, 0xfffffee),
+    (28, 0xfffffef),
+    (28, 0xffffff0),
+    (28, 0xffffff1),
+    (28, 0xffffff2),
+    (30, 0x3ffffffe),
+    (28, 0xffffff3),
+    (28, 0xffffff4),
+    (28, 0xffffff5),
+    (28, 0xffffff6),
+    (28, 0xffffff7),
+    (28, 0xffffff8),
+    (28, 0xffffff9),
+    (28, 0xffffffa),
+    (28, 0xffffffb),
+    (6, 0x14),
+    (10, 0x3f8),
+    (10, 0x3f9),
+    (12, 0xffa),
+    (13, 0x1ff9),
+    (6, 0x15),
+    (8, 0xf8),
+    (11, 0x7fa),
+    (10, 0x3fa),
+    (10, 0x3fb),
+    (8, 0xf9),
+    (11, 0x7fb),
+    (8, 0xfa),
+    (6, 0x16),
+    (6, 0x17),
+    (6, 0x18),
+    (5, 0x0),
+    (5, 0x1),
+    (5, 0x2),
+    (6, 0x19),
+    (6, 0x1a),
+    (6, 0x1b),
+    (6, 0x1c),
+    (6, 0x1d),
+    (6, 0x1e),
+    (6, 0x1f),
+    (7, 0x5c),
+    (8, 0xfb),
+    (15, 0x7ffc),
+    (6, 0x20),
+    (12, 0xffb),
+    (10, 0x3fc),
+    (13, 0x1ffa),
+    (6, 0x21),
+    (7, 0x5d),
+    (7, 0x5e),
+    (7, 0x5f),
+    (7, 0x60),
+    (7, 0x61),
+    (7, 0x62),
+    (7, 0x63),
+    (7, 0x64),
+    (7, 0x65),
+    (7, 0x66),
+    (7, 0x67),
+    (7, 0x68),
+    (7, 0x69),
+    (7, 0x6a),
+    (7, 0x6b),
+    (7, 0x6c),
+    (7, 0x6d),
+    (7, 0x6e),
+    (7, 0x6f),
+    (7, 0x70),
+    (7, 0x71),
+    (7, 0x72),
+    (8, 0xfc),
+    (7, 0x73),
+    (8, 0xfd),
+    (13, 0x1ffb),
+    (19, 0x7fff0),
+    (13, 0x1ffc),
+    (14, 0x3ffc),
+    (6, 0x22),
+    (15, 0x7ffd),
+    (5, 0x3),
+    (6, 0x23),
+    (5, 0x4),
+    (6, 0x24),
+    (5, 0x5),
+    (6, 0x25),
+    (6, 0x26),
+    (6, 0x27),
+    (5, 0x6),
+    (7, 0x74),
+    (7, 0x75),
+    (6, 0x28),
+    (6, 0x29),
+    (6, 0x2a),
+    (5, 0x7),
+    (6, 0x2b),
+    (7, 0x76),
+    (6, 0x2c),
+    (5, 0x8),
+    (5, 0x9),
+    (6, 0x2d),
+    (7, 0x77),
+    (7, 0x78),
+    (7, 0x79),
+    (7, 0x7a),
+    (7, 0x7b),
+    (15, 0x7ffe),
+    (11, 0x7fc),
+    (14, 0x3ffd),
+    (13, 0x1ffd),
+    (28, 0xffffffc),
+    (20, 0xfffe6),
+    (22, 0x3fffd2),
+    (20, 0xfffe7),
+    (20, 0xfffe8),
+    (22, 0x3fffd3),
+    (22, 0x3fffd4),
+    (22, 0x3fffd5),
+    (23, 0x7fffd9),
+    (22, 0x3fffd6),
+    (23, 0x7fffda),
+    (23, 0x7fffdb),
+    (23, 0x7fffdc),
+    (23, 0x7fffdd),
+    (23, 0x7fffde),
+    (24, 0xffffeb),
+    (23, 0x7fffdf),
+    (24, 0xffffec),
+    (24, 0xffffed),
+    (22, 0x3fffd7),
+    (23, 0x7fffe0),
+    (24, 0xffffee),
+    (23, 0x7fffe1),
+    (23, 0x7fffe2),
+    (23, 0x7fffe3),
+    (23, 0x7fffe4),
+    (21, 0x1fffdc),
+    (22, 0x3fffd8),
+    (23, 0x7fffe5),
+    (22, 0x3fffd9),
+    (23, 0x7fffe6),
+    (23, 0x7fffe7),
+    (24, 0xffffef),
+    (22, 0x3fffda),
+    (21, 0x1fffdd),
+    (20, 0xfffe9),
+    (22, 0x3fffdb),
+    (22, 0x3fffdc),
+    (23, 0x7fffe8),
+    (23, 0x7fffe9),
+    (21, 0x1fffde),
+    (23, 0x7fffea),
+    (22, 0x3fffdd),
+    (22, 0x3fffde),
+    (24, 0xfffff0),
+    (21, 0x1fffdf),
+    (22, 0x3fffdf),
+    (23, 0x7fffeb),
+    (23, 0x7fffec),
+    (21, 0x1fffe0),
+    (21, 0x1fffe1),
+    (22, 0x3fffe0),
+    (21, 0x1fffe2),
+    (23, 0x7fffed),
+    (22, 0x3fffe1),
+    (23, 0x7fffee),
+    (23, 0x7fffef),
+    (20, 0xfffea),
+    (22, 0x3fffe2),
+    (22, 0x3fffe3),
+    (22, 0x3fffe4),
+    (23, 0x7ffff0),
+    (22, 0x3fffe5),
+    (22, 0x3fffe6),
+    (23, 0x7ffff1),
+    (26, 0x3ffffe0),
+    (26, 0x3ffffe1),
+    (20, 0xfffeb),
+    (19, 0x7fff1),
+    (22, 0x3fffe7),
+    (23, 0x7ffff2),
+    (22, 0x3fffe8),
+    (25, 0x1ffffec),
+    (26, 0x3ffffe2),
+    (26, 0x3ffffe3),
+    (26, 0x3ffffe4),
+    (27, 0x7ffffde),
+    (27, 0x7ffffdf),
+    (26, 0x3ffffe5),
+    (24, 0xfffff1),
+    (25, 0x1ffffed),
+    (19, 0x7fff2),
+    (21, 0x1fffe3),
+    (26, 0x3ffffe6),
+    (27, 0x7ffffe0),
+    (27, 0x7ffffe1),
+    (26, 0x3ffffe7),
+    (27, 0x7ffffe2),
+    (24, 0xfffff2),
+    (21, 0x1fffe4),
+    (21, 0x1fffe5),
+    (26, 0x3ffffe8),
+    (26, 0x3ffffe9),
+    (28, 0xffffffd),
+    (27, 0x7ffffe3),
+    (27, 0x7ffffe4),
+    (27, 0x7ffffe5),
+    (20, 0xfffec),
+    (24, 0xfffff3),
+    (20, 0xfffed),
+    (21, 0x1fffe6),
+    (22, 0x3fffe9),
+    (21, 0x1fffe7),
+    (21, 0x1fffe8),
+    (23, 0x7ffff3),
+    (22, 0x3fffea),
+    (22, 0x3fffeb),
+    (25, 0x1ffffee),
+    (25, 0x1ffffef),
+    (24, 0xfffff4),
+    (24, 0xfffff5),
+    (26, 0x3ffffea),
+    (23, 0x7ffff4),
+    (26, 0x3ffffeb),
+    (27, 0x7ffffe6),
+    (26, 0x3ffffec),
+    (26, 0x3ffffed),
+    (27, 0x7ffffe7),
+    (27, 0x7ffffe8),
+    (27, 0x7ffffe9),
+    (27, 0x7ffffea),
+    (27, 0x7ffffeb),
+    (28, 0xffffffe),
+    (27, 0x7ffffec),
+    (27, 0x7ffffed),
+    (27, 0x7ffffee),
+    (27, 0x7ffffef),
+    (27, 0x7fffff0),
+    (26, 0x3ffffee),
+    (30, 0x3fffffff),
+];
+
+/// Canonical-code index over `HPACK_HUFFMAN_CODES`: for each bit length, the
+/// first code, how many codes have that length, and where their symbols start
+/// in `symbols` (ordered by code).
+struct HuffmanDecodeTable {
+    first_code: [u32; 31],
+    count: [u16; 31],
+    offset: [u16; 31],
+    symbols: [u16; 257],
+}
+
+const fn build_huffman_decode_table() -> HuffmanDecodeTable {
+    let mut table = HuffmanDecodeTable {
+        first_code: [0; 31],
+        count: [0; 31],
+        offset: [0; 31],
+        symbols: [0; 257],
+    };
+    let mut next = 0usize;
+    let mut len = 1usize;
+    while len <= 30 {
+        table.offset[len] = next as u16;
+        let mut found = false;
+        // Codes of one length are consecutive, so emit them in code order.
+        let mut code_min = u32::MAX;
+        let mut sym = 0usize;
+        while sym < HPACK_HUFFMAN_CODES.len() {
+            let (l, code) = HPACK_HUFFMAN_CODES[sym];
+            if l as usize == len && code < code_min {
+                code_min = code;
+                found = true;
+            }
+            sym += 1;
+        }
+        if found {
+            table.first_code[len] = code_min;
+            let mut code = code_min;
+            loop {
+                let mut hit = false;
+                let mut sym = 0usize;
+                while sym < HPACK_HUFFMAN_CODES.len() {
+                    let (l, c) = HPACK_HUFFMAN_CODES[sym];
+                    if l as usize == len && c == code {
+                        table.symbols[next] = sym as u16;
+                        next += 1;
+                        table.count[len] += 1;
+                        hit = true;
+                        break;
+                    }
+                    sym += 1;
+                }
+                if !hit {
+                    break;
+                }
+                code += 1;
+            }
+        }
+        len += 1;
+    }
+    table
+}
+
+static HPACK_HUFFMAN_DECODE: HuffmanDecodeTable = build_huffman_decode_table();
+
+fn hpack_huffman_lookup_symbol(prefix: u32, bit_len: u8) -> Option<u16> {
+    let table = &HPACK_HUFFMAN_DECODE;
+    let len = bit_len as usize;
+    if len > 30 {
+        return None;
+    }
+    let rel = prefix.checked_sub(table.first_code[len])?;
+    if rel >= table.count[len] as u32 {
+        return None;
+    }
+    Some(table.symbols[table.offset[len] as usize + rel as usize])
+}
+
+pub fn hpack_decode_huffman(raw: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(raw.len());
+    let mut acc: u64 = 0;
+    let mut bit_len: u8 = 0;
+
+    for byte in raw.iter().copied() {
+        if bit_len > 56 {
+            return None;
+        }
+        acc = (acc << 8) | byte as u64;
+        bit_len = bit_len.saturating_add(8);
+
+        loop {
+            let mut matched = false;
+            let mut l = 5u8;
+            while l <= 30 {
+                if bit_len < l {
+                    l += 1;
+                    continue;
+                }
+
+                let mask = (1u64 << l) - 1;
+                let prefix = ((acc >> (bit_len - l)) & mask) as u32;
+                if let Some(sym) = hpack_huffman_lookup_symbol(prefix, l) {
+                    if sym == 256 {
+                        return None;
+                    }
+                    out.push(sym as u8);
+                    bit_len -= l;
+                    if bit_len == 0 {
+                        acc = 0;
+                    } else {
+                        let keep_mask = (1u64 << bit_len) - 1;
+                        acc &= keep_mask;
+                    }
+                    matched = true;
+                    break;
+                }
+                l += 1;
+            }
+
+            if !matched {
+                break;
+            }
+        }
+    }
+
+    // RFC7541: remaining bits must be <= 7 and all ones (EOS padding).
+    if bit_len > 7 {
+        return None;
+    }
+    if bit_len > 0 {
+        let pad_mask = (1u64 << bit_len) - 1;
+        if (acc & pad_mask) != pad_mask {
+            return None;
+        }
+    }
+
+    Some(out)
+}
+
+pub fn hpack_decode_prefixed_integer(buf: &[u8], idx: &mut usize, prefix_bits: u8) -> Option<u32> {
+    let first = *buf.get(*idx)?;
+    if prefix_bits == 0 || prefix_bits > 8 {
+        return None;
+    }
+    let mask = u8::MAX >> (8 - prefix_bits);
+    let mut value = (first & mask) as u32;
+    *idx += 1;
+    if value < mask as u32 {
+        return Some(value);
+    }
+
+    let mut shift = 0u32;
+    loop {
+        let b = *buf.get(*idx)?;
+        *idx += 1;
+        let part = ((b & 0x7F) as u32).checked_shl(shift)?;
+        if part >> shift != (b & 0x7F) as u32 {
+            return None;
+        }
+        value = value.checked_add(part)?;
+        if (b & 0x80) == 0 {
+            break;
+        }
+        shift = shift.saturating_add(7);
+        if shift > 28 {
+            return None;
+        }
+    }
+    Some(value)
+}
+
+pub fn hpack_decode_string(buf: &[u8], idx: &mut usize) -> Option<String> {
+    let first = *buf.get(*idx)?;
+    let huffman = (first & 0x80) != 0;
+    let len = hpack_decode_prefixed_integer(buf, idx, 7)? as usize;
+    let end = idx.checked_add(len)?;
+    if end > buf.len() {
+        return None;
+    }
+    let raw = &buf[*idx..end];
+    *idx = end;
+
+    if huffman {
+        let decoded = hpack_decode_huffman(raw)?;
+        return Some(String::from_utf8_lossy(decoded.as_slice()).into_owned());
+    }
+
+    Some(String::from_utf8_lossy(raw).into_owned())
+}
+
+pub fn hpack_static_table(index: u32) -> Option<(&'static str, &'static str)> {
+    // RFC 7541 Appendix A (1..=61)
+    match index {
+        1 => Some((":authority", "")),
+        2 => Some((":method", "GET")),
+        3 => Some((":method", "POST")),
+        4 => Some((":path", "/")),
+        5 => Some((":path", "/index.html")),
+        6 => Some((":scheme", "http")),
+        7 => Some((":scheme", "https")),
+        8 => Some((":status", "200")),
+        9 => Some((":status", "204")),
+        10 => Some((":status", "206")),
+        11 => Some((":status", "304")),
+        12 => Some((":status", "400")),
+        13 => Some((":status", "404")),
+        14 => Some((":status", "500")),
+        15 => Some(("accept-charset", "")),
+        16 => Some(("accept-encoding", "gzip, deflate")),
+        17 => Some(("accept-language", "")),
+        18 => Some(("accept-ranges", "")),
+        19 => Some(("accept", "")),
+        20 => Some(("access-control-allow-origin", "")),
+        21 => Some(("age", "")),
+        22 => Some(("allow", "")),
+        23 => Some(("authorization", "")),
+        24 => Some(("cache-control", "")),
+        25 => Some(("content-disposition", "")),
+        26 => Some(("content-encoding", "")),
+        27 => Some(("content-language", "")),
+        28 => Some(("content-length", "")),
+        29 => Some(("content-location", "")),
+        30 => Some(("content-range", "")),
+        31 => Some(("content-type", "")),
+        32 => Some(("cookie", "")),
+        33 => Some(("date", "")),
+        34 => Some(("etag", "")),
+        35 => Some(("expect", "")),
+        36 => Some(("expires", "")),
+        37 => Some(("from", "")),
+        38 => Some(("host", "")),
+        39 => Some(("if-match", "")),
+        40 => Some(("if-modified-since", "")),
+        41 => Some(("if-none-match", "")),
+        42 => Some(("if-range", "")),
+        43 => Some(("if-unmodified-since", "")),
+        44 => Some(("last-modified", "")),
+        45 => Some(("link", "")),
+        46 => Some(("location", "")),
+        47 => Some(("max-forwards", "")),
+        48 => Some(("proxy-authenticate", "")),
+        49 => Some(("proxy-authorization", "")),
+        50 => Some(("range", "")),
+        51 => Some(("referer", "")),
+        52 => Some(("refresh", "")),
+        53 => Some(("retry-after", "")),
+        54 => Some(("server", "")),
+        55 => Some(("set-cookie", "")),
+        56 => Some(("strict-transport-security", "")),
+        57 => Some(("transfer-encoding", "")),
+        58 => Some(("user-agent", "")),
+        59 => Some(("vary", "")),
+        60 => Some(("via", "")),
+        61 => Some(("www-authenticate", "")),
+        _ => None,
+    }
+}
+
+pub fn hpack_lookup_header(index: u32, dynamic: &HpackDynamicTable) -> Option<(String, String)> {
+    if index == 0 {
+        return None;
+    }
+    if let Some((n, v)) = hpack_static_table(index) {
+        return Some((String::from(n), String::from(v)));
+    }
+    dynamic
+        .get(index)
+        .map(|(n, v)| (String::from(n), String::from(v)))
+}
+
+pub fn hpack_lookup_name(index: u32, dynamic: &HpackDynamicTable) -> Option<String> {
+    if index == 0 {
+        return None;
+    }
+    if let Some((n, _)) = hpack_static_table(index) {
+        return Some(String::from(n));
+    }
+    dynamic.get(index).map(|(n, _)| String::from(n))
+}
+
+/// Decode a complete header block into `out_headers` (pseudo-headers other
+/// than `:status` are dropped). Returns `false` when the block is malformed;
+/// decoding stops there since the remaining bytes cannot be resynchronised.
+pub fn hpack_decode_header_block(
+    block: &[u8],
+    dynamic: &mut HpackDynamicTable,
+    out_headers: &mut Vec<(String, String)>,
+    out_status: &mut Option<u16>,
+) -> bool {
+    let mut idx = 0usize;
+    while idx < block.len() {
+        let byte = block[idx];
+
+        // Dynamic table size update: 001xxxxx
+        if (byte & 0xE0) == 0x20 {
+            let Some(new_size) = hpack_decode_prefixed_integer(block, &mut idx, 5) else {
+                return false;
+            };
+            dynamic.set_max_size(new_size as usize);
+            continue;
+        }
+
+        // Indexed header field: 1xxxxxxx
+        if (byte & 0x80) != 0 {
+            let Some(index) = hpack_decode_prefixed_integer(block, &mut idx, 7) else {
+                return false;
+            };
+            let Some((name, value)) = hpack_lookup_header(index, dynamic) else {
+                return false;
+            };
+            if name == ":status" {
+                if let Ok(code) = value.parse::<u16>() {
+                    *out_status = Some(code);
+                }
+            } else if !name.starts_with(':') {
+                out_headers.push((name, value));
+            }
+            continue;
+        }
+
+        // Literal header field:
+        // 01xxxxxx (incremental indexing) or 0000xxxx (without indexing) or 0001xxxx (never indexed)
+        let (incremental, prefix) = if (byte & 0xC0) == 0x40 {
+            (true, 6u8)
+        } else {
+            (false, 4u8)
+        };
+        let Some(name_index) = hpack_decode_prefixed_integer(block, &mut idx, prefix) else {
+            return false;
+        };
+
+        let name = if name_index == 0 {
+            hpack_decode_string(block, &mut idx)
+        } else {
+            hpack_lookup_name(name_index, dynamic)
+        };
+        let Some(name) = name else {
+            return false;
+        };
+        let Some(value) = hpack_decode_string(block, &mut idx) else {
+            return false;
+        };
+
+        if name == ":status" {
+            if let Ok(code) = value.parse::<u16>() {
+                *out_status = Some(code);
+            }
+        } else if !name.starts_with(':') {
+            out_headers.push((name.clone(), value.clone()));
+        }
+
+        if incremental {
+            dynamic.insert(name, value);
+        }
+    }
+    true
+}
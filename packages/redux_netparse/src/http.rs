@@ -0,0 +1,224 @@
+//! HTTP/1.x response heads, `Transfer-Encoding: chunked` bodies and the
+//! request rules that depend on the method (redirects, retries, 1xx).
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::str;
+
+use crate::ascii_lowercase;
+
+#[derive(Clone, Default)]
+pub struct ParsedHttpHeaders {
+    pub status_code: Option<u16>,
+    pub status_line: Option<String>,
+    /// Header names are lowercased; values are trimmed.
+    pub headers: Vec<(String, String)>,
+    pub body_offset: usize,
+}
+
+/// `(head_end, body_offset)` of the first blank line (`\r\n\r\n` or `\n\n`).
+pub fn find_http_header_end(raw: &[u8]) -> Option<(usize, usize)> {
+    let mut i = 0usize;
+    while i + 3 < raw.len() {
+        if raw[i] == b'\r' && raw[i + 1] == b'\n' && raw[i + 2] == b'\r' && raw[i + 3] == b'\n' {
+            return Some((i, i + 4));
+        }
+        i += 1;
+    }
+    let mut j = 0usize;
+    while j + 1 < raw.len() {
+        if raw[j] == b'\n' && raw[j + 1] == b'\n' {
+            return Some((j, j + 2));
+        }
+        j += 1;
+    }
+    None
+}
+
+/// Parse the status line and headers. Without a complete head the result is
+/// empty and `body_offset` is 0.
+pub fn parse_http_headers(raw: &[u8]) -> ParsedHttpHeaders {
+    let mut parsed = ParsedHttpHeaders::default();
+    let Some((head_end, body_offset)) = find_http_header_end(raw) else {
+        return parsed;
+    };
+    parsed.body_offset = body_offset;
+
+    let head_bytes = &raw[..head_end];
+    let head = String::from_utf8_lossy(head_bytes).into_owned();
+    let mut lines = head.lines();
+    if let Some(status_line) = lines.next() {
+        let status_trimmed = status_line.trim();
+        parsed.status_line = Some(String::from(status_trimmed));
+        let mut parts = status_trimmed.split_whitespace();
+        let _proto = parts.next();
+        parsed.status_code = parts.next().and_then(|s| s.parse::<u16>().ok());
+    }
+    for line in lines {
+        let l = line.trim();
+        if l.is_empty() {
+            continue;
+        }
+        if let Some((k, v)) = l.split_once(':') {
+            parsed
+                .headers
+                .push((ascii_lowercase(k.trim()), String::from(v.trim())));
+        }
+    }
+    parsed
+}
+
+pub fn header_first<'a>(headers: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    let key_lower = ascii_lowercase(key);
+    for (k, v) in headers.iter() {
+        if *k == key_lower {
+            return Some(v.as_str());
+        }
+    }
+    None
+}
+
+pub fn header_values<'a>(headers: &'a [(String, String)], key: &str) -> Vec<&'a str> {
+    let key_lower = ascii_lowercase(key);
+    let mut out = Vec::new();
+    for (k, v) in headers.iter() {
+        if *k == key_lower {
+            out.push(v.as_str());
+        }
+    }
+    out
+}
+
+/// Reassemble a chunked body. A chunk that runs past the end of `body` or a
+/// size line that is not hex makes the whole body invalid.
+pub fn http_decode_chunked_body(body: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut i = 0usize;
+    while i < body.len() {
+        let line_end_rel = body[i..]
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .or_else(|| body[i..].iter().position(|b| *b == b'\n'))?;
+        let line_end = i + line_end_rel;
+        let line_bytes = if body.get(line_end) == Some(&b'\r') {
+            &body[i..line_end]
+        } else {
+            &body[i..=line_end]
+        };
+        let line_text = str::from_utf8(line_bytes).ok()?.trim();
+        let size_hex = line_text.split(';').next().unwrap_or("").trim();
+        let chunk_size = usize::from_str_radix(size_hex, 16).ok()?;
+
+        let after_line = if body.get(line_end) == Some(&b'\r') {
+            line_end + 2
+        } else {
+            line_end + 1
+        };
+
+        if chunk_size == 0 {
+            return Some(out);
+        }
+        let chunk_end = after_line.checked_add(chunk_size)?;
+        if chunk_end > body.len() {
+            return None;
+        }
+        out.extend_from_slice(&body[after_line..chunk_end]);
+        i = chunk_end;
+        if i + 1 < body.len() && body[i] == b'\r' && body[i + 1] == b'\n' {
+            i += 2;
+        } else if i < body.len() && body[i] == b'\n' {
+            i += 1;
+        }
+    }
+    Some(out)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HttpMethod {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Patch,
+    Options,
+}
+
+impl HttpMethod {
+    /// Case-insensitive method name; `None` for anything else.
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim();
+        [
+            HttpMethod::Get,
+            HttpMethod::Head,
+            HttpMethod::Post,
+            HttpMethod::Put,
+            HttpMethod::Delete,
+            HttpMethod::Patch,
+            HttpMethod::Options,
+        ]
+        .into_iter()
+        .find(|m| name.eq_ignore_ascii_case(m.as_str()))
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Head => "HEAD",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Delete => "DELETE",
+            HttpMethod::Patch => "PATCH",
+            HttpMethod::Options => "OPTIONS",
+        }
+    }
+
+    /// RFC 9110 9.2.2: repeating the request has the same effect as sending
+    /// it once, so it may be retried after a failure.
+    pub const fn is_idempotent(self) -> bool {
+        !matches!(self, HttpMethod::Post | HttpMethod::Patch)
+    }
+
+    /// Methods whose request always carries `Content-Length`, even when the
+    /// body is empty.
+    pub const fn has_body(self) -> bool {
+        matches!(self, HttpMethod::Post | HttpMethod::Put | HttpMethod::Patch)
+    }
+}
+
+/// A request may be sent again if it never reached the server or if it is
+/// idempotent.
+pub fn request_retry_allowed(method: HttpMethod, request_sent: bool) -> bool {
+    !request_sent || method.is_idempotent()
+}
+
+/// Method for the request that follows a redirect with `status`, and whether
+/// the body goes with it; `None` when `status` is not a redirect. 301 and 302
+/// turn POST into GET as browsers do, 303 turns everything but HEAD into
+/// GET, and 307/308 repeat the request unchanged (RFC 9110 15.4).
+pub fn redirect_method(method: HttpMethod, status: u16) -> Option<(HttpMethod, bool)> {
+    match status {
+        301 | 302 if method == HttpMethod::Post => Some((HttpMethod::Get, false)),
+        301 | 302 => Some((method, true)),
+        303 if method == HttpMethod::Head => Some((HttpMethod::Head, false)),
+        303 => Some((HttpMethod::Get, false)),
+        307 | 308 => Some((method, true)),
+        _ => None,
+    }
+}
+
+/// Bytes taken by complete interim (1xx) responses at the start of `raw`,
+/// such as `100 Continue` or `103 Early Hints`. `101 Switching Protocols`
+/// is final and is not skipped.
+pub fn interim_responses_len(raw: &[u8]) -> usize {
+    let mut offset = 0usize;
+    loop {
+        let parsed = parse_http_headers(&raw[offset..]);
+        match parsed.status_code {
+            Some(code) if parsed.body_offset != 0 && (100..200).contains(&code) && code != 101 => {
+                offset += parsed.body_offset;
+            }
+            _ => return offset,
+        }
+    }
+}
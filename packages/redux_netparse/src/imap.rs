@@ -0,0 +1,199 @@
+//! IMAP4rev1 (RFC 3501) responses: lines with literals, tagged
+//! completions, `EXISTS` and `FETCH` data, and argument quoting.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::starts_with_ignore_ascii_case;
+
+pub const IMAP_DEFAULT_PORT: u16 = 143;
+pub const IMAPS_DEFAULT_PORT: u16 = 993;
+
+/// One logical response line. Literals (`{n}` followed by n raw bytes) are
+/// cut out of the text and kept in order in `literals`; the `{n}` marker
+/// stays in `text`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImapLine {
+    pub text: String,
+    pub literals: Vec<Vec<u8>>,
+}
+
+fn literal_len(segment: &[u8]) -> Option<usize> {
+    // "... {123}" or the non-synchronising "{123+}"
+    let body = segment.strip_suffix(b"}")?;
+    let open = body.iter().rposition(|&b| b == b'{')?;
+    let digits = &body[open + 1..];
+    let digits = digits.strip_suffix(b"+").unwrap_or(digits);
+    if digits.is_empty() || digits.len() > 9 || !digits.iter().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    core::str::from_utf8(digits).ok()?.parse().ok()
+}
+
+/// Parse the first complete logical line of `buf`. Returns the line and the
+/// number of bytes it used, or `None` while more data is needed.
+pub fn parse_imap_line(buf: &[u8]) -> Option<(ImapLine, usize)> {
+    let mut line = ImapLine::default();
+    let mut pos = 0usize;
+    loop {
+        let rest = buf.get(pos..)?;
+        let end = rest.iter().position(|&b| b == b'\n')?;
+        let segment = &rest[..end];
+        let segment = segment.strip_suffix(b"\r").unwrap_or(segment);
+        line.text.push_str(String::from_utf8_lossy(segment).as_ref());
+        pos += end + 1;
+        let Some(len) = literal_len(segment) else {
+            return Some((line, pos));
+        };
+        let literal = buf.get(pos..pos.checked_add(len)?)?;
+        line.literals.push(literal.to_vec());
+        pos += len;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImapStatus {
+    Ok,
+    No,
+    Bad,
+}
+
+/// Everything the server sent for one command, up to its tagged completion.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImapResponse {
+    pub status: ImapStatus,
+    /// Text after the status word of the tagged line.
+    pub text: String,
+    /// Untagged (`* ...`) lines, without the leading `* `.
+    pub untagged: Vec<ImapLine>,
+    pub consumed: usize,
+}
+
+fn split_status(text: &str) -> Option<(ImapStatus, &str)> {
+    let (word, rest) = text.split_once(' ').unwrap_or((text, ""));
+    let status = if word.eq_ignore_ascii_case("OK") {
+        ImapStatus::Ok
+    } else if word.eq_ignore_ascii_case("NO") {
+        ImapStatus::No
+    } else if word.eq_ignore_ascii_case("BAD") {
+        ImapStatus::Bad
+    } else {
+        return None;
+    };
+    Some((status, rest.trim()))
+}
+
+/// Collect the response to the command tagged `tag`. `None` until the tagged
+/// line has arrived. Continuation requests (`+ ...`) are skipped; the client
+/// never sends synchronising literals.
+pub fn parse_imap_response(buf: &[u8], tag: &str) -> Option<ImapResponse> {
+    let mut pos = 0usize;
+    let mut untagged = Vec::new();
+    loop {
+        let (line, used) = parse_imap_line(buf.get(pos..)?)?;
+        pos += used;
+        if let Some(rest) = line.text.strip_prefix("* ") {
+            untagged.push(ImapLine {
+                text: String::from(rest),
+                literals: line.literals,
+            });
+            continue;
+        }
+        let Some(rest) = line.text.strip_prefix(tag).and_then(|r| r.strip_prefix(' ')) else {
+            continue;
+        };
+        let (status, text) = split_status(rest).unwrap_or((ImapStatus::Bad, rest));
+        return Some(ImapResponse {
+            status,
+            text: String::from(text),
+            untagged,
+            consumed: pos,
+        });
+    }
+}
+
+/// The untagged greeting: `Some(true)` for `OK`/`PREAUTH`, `Some(false)` for
+/// `BYE` or anything else, with the bytes used.
+pub fn parse_imap_greeting(buf: &[u8]) -> Option<(bool, usize)> {
+    let (line, used) = parse_imap_line(buf)?;
+    let ok = starts_with_ignore_ascii_case(line.text.as_str(), "* OK")
+        || starts_with_ignore_ascii_case(line.text.as_str(), "* PREAUTH");
+    Some((ok, used))
+}
+
+/// Quote a command argument. `None` for text that cannot go in a quoted
+/// string (CR, LF, NUL); the client does not send literals.
+pub fn imap_quote(text: &str) -> Option<String> {
+    if text.bytes().any(|b| b == b'\r' || b == b'\n' || b == 0) {
+        return None;
+    }
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        if c == '"' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+    Some(out)
+}
+
+/// Message count from an untagged `23 EXISTS`.
+pub fn parse_exists(line: &ImapLine) -> Option<u32> {
+    let (count, word) = line.text.trim().split_once(' ')?;
+    if !word.trim().eq_ignore_ascii_case("EXISTS") {
+        return None;
+    }
+    count.parse().ok()
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImapFetch {
+    pub seq: u32,
+    pub uid: Option<u32>,
+    pub size: Option<u32>,
+    pub seen: bool,
+    /// First literal of the response: the requested `BODY[...]` section.
+    pub body: Option<Vec<u8>>,
+}
+
+fn number_after(text: &str, key: &str) -> Option<u32> {
+    let upper = text.to_ascii_uppercase();
+    let mut search = 0usize;
+    while let Some(found) = upper[search..].find(key) {
+        let at = search + found;
+        let boundary_before = at == 0 || matches!(upper.as_bytes()[at - 1], b' ' | b'(');
+        let after = &text[at + key.len()..];
+        if boundary_before && after.starts_with(' ') {
+            let digits: &str = after.trim_start().split(|c: char| !c.is_ascii_digit()).next().unwrap_or("");
+            if let Ok(n) = digits.parse() {
+                return Some(n);
+            }
+        }
+        search = at + key.len();
+    }
+    None
+}
+
+/// Parse an untagged `12 FETCH (...)` line.
+pub fn parse_fetch(line: &ImapLine) -> Option<ImapFetch> {
+    let text = line.text.as_str();
+    let (seq, rest) = text.split_once(' ')?;
+    let seq = seq.parse().ok()?;
+    if !starts_with_ignore_ascii_case(rest.trim_start(), "FETCH") {
+        return None;
+    }
+    let flags = rest
+        .to_ascii_uppercase()
+        .find("FLAGS (")
+        .and_then(|at| rest[at + 7..].split(')').next().map(String::from))
+        .unwrap_or_default();
+    Some(ImapFetch {
+        seq,
+        uid: number_after(rest, "UID"),
+        size: number_after(rest, "RFC822.SIZE"),
+        seen: flags.split_whitespace().any(|f| f.eq_ignore_ascii_case("\\Seen")),
+        body: line.literals.first().cloned(),
+    })
+}
@@ -0,0 +1,370 @@
+//! IPP messages (RFC 8010 encoding, RFC 8011 semantics): building requests
+//! and parsing the attribute groups of responses.
+//!
+//! A message is a version, an operation id (requests) or status code
+//! (responses), a request id, then attribute groups, each opened by a
+//! delimiter tag, and `end-of-attributes-tag`. Anything after that is
+//! document data.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub const IPP_VERSION_2_0: (u8, u8) = (2, 0);
+
+pub const IPP_OP_PRINT_JOB: u16 = 0x0002;
+pub const IPP_OP_VALIDATE_JOB: u16 = 0x0004;
+pub const IPP_OP_CANCEL_JOB: u16 = 0x0008;
+pub const IPP_OP_GET_JOB_ATTRIBUTES: u16 = 0x0009;
+pub const IPP_OP_GET_PRINTER_ATTRIBUTES: u16 = 0x000B;
+
+pub const IPP_TAG_OPERATION: u8 = 0x01;
+pub const IPP_TAG_JOB: u8 = 0x02;
+pub const IPP_TAG_END: u8 = 0x03;
+pub const IPP_TAG_PRINTER: u8 = 0x04;
+pub const IPP_TAG_UNSUPPORTED_GROUP: u8 = 0x05;
+
+pub const IPP_TAG_UNSUPPORTED_VALUE: u8 = 0x10;
+pub const IPP_TAG_UNKNOWN: u8 = 0x12;
+pub const IPP_TAG_NO_VALUE: u8 = 0x13;
+pub const IPP_TAG_INTEGER: u8 = 0x21;
+pub const IPP_TAG_BOOLEAN: u8 = 0x22;
+pub const IPP_TAG_ENUM: u8 = 0x23;
+pub const IPP_TAG_OCTET_STRING: u8 = 0x30;
+pub const IPP_TAG_RESOLUTION: u8 = 0x32;
+pub const IPP_TAG_RANGE: u8 = 0x33;
+pub const IPP_TAG_BEGIN_COLLECTION: u8 = 0x34;
+pub const IPP_TAG_END_COLLECTION: u8 = 0x37;
+pub const IPP_TAG_TEXT: u8 = 0x41;
+pub const IPP_TAG_NAME: u8 = 0x42;
+pub const IPP_TAG_KEYWORD: u8 = 0x44;
+pub const IPP_TAG_URI: u8 = 0x45;
+pub const IPP_TAG_CHARSET: u8 = 0x47;
+pub const IPP_TAG_LANGUAGE: u8 = 0x48;
+pub const IPP_TAG_MIME_TYPE: u8 = 0x49;
+pub const IPP_TAG_MEMBER_NAME: u8 = 0x4A;
+
+pub const IPP_RESOLUTION_DPI: u8 = 3;
+
+/// `job-state` values (RFC 8011 5.3.7).
+pub const IPP_JOB_PENDING: i32 = 3;
+pub const IPP_JOB_HELD: i32 = 4;
+pub const IPP_JOB_PROCESSING: i32 = 5;
+pub const IPP_JOB_STOPPED: i32 = 6;
+pub const IPP_JOB_CANCELED: i32 = 7;
+pub const IPP_JOB_ABORTED: i32 = 8;
+pub const IPP_JOB_COMPLETED: i32 = 9;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IppValue {
+    Integer(i32),
+    Boolean(bool),
+    Enum(i32),
+    Resolution { x: i32, y: i32, units: u8 },
+    Range(i32, i32),
+    /// Text, name, keyword, uri, charset, language and mime type values,
+    /// with their tag.
+    Text(u8, String),
+    /// Collections are skipped; the value only marks that one was there.
+    Collection,
+    Other(u8, Vec<u8>),
+}
+
+impl IppValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            IppValue::Text(_, text) => Some(text.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i32> {
+        match self {
+            IppValue::Integer(v) | IppValue::Enum(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IppAttribute {
+    /// Delimiter tag of the group the attribute is in.
+    pub group: u8,
+    pub name: String,
+    pub values: Vec<IppValue>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IppMessage {
+    pub version: (u8, u8),
+    /// Operation id in a request, status code in a response.
+    pub code: u16,
+    pub request_id: u32,
+    pub attributes: Vec<IppAttribute>,
+    /// Offset of the document data after the end tag.
+    pub data_offset: usize,
+}
+
+impl IppMessage {
+    /// First attribute called `name` in group `group` (any group for 0).
+    pub fn attribute(&self, group: u8, name: &str) -> Option<&IppAttribute> {
+        self.attributes
+            .iter()
+            .find(|a| (group == 0 || a.group == group) && a.name == name)
+    }
+
+    pub fn first_str(&self, group: u8, name: &str) -> Option<&str> {
+        self.attribute(group, name)?.values.first()?.as_str()
+    }
+
+    pub fn first_int(&self, group: u8, name: &str) -> Option<i32> {
+        self.attribute(group, name)?.values.first()?.as_int()
+    }
+
+    /// Every string value of `name`, e.g. `document-format-supported`.
+    pub fn strings(&self, group: u8, name: &str) -> Vec<&str> {
+        self.attribute(group, name)
+            .map(|a| a.values.iter().filter_map(|v| v.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn is_successful(&self) -> bool {
+        self.code < 0x0100
+    }
+}
+
+/// Request encoder. Groups and attributes are written in call order;
+/// `finish` adds the end tag and the document data.
+pub struct IppRequest {
+    buf: Vec<u8>,
+}
+
+impl IppRequest {
+    /// A request with the operation group opened and the mandatory
+    /// `attributes-charset` / `attributes-natural-language` written.
+    pub fn new(operation: u16, request_id: u32, language: &str) -> Self {
+        let mut request = IppRequest { buf: Vec::with_capacity(256) };
+        request.buf.extend_from_slice(&[IPP_VERSION_2_0.0, IPP_VERSION_2_0.1]);
+        request.buf.extend_from_slice(&operation.to_be_bytes());
+        request.buf.extend_from_slice(&request_id.to_be_bytes());
+        request.group(IPP_TAG_OPERATION);
+        request.string(IPP_TAG_CHARSET, "attributes-charset", "utf-8");
+        request.string(IPP_TAG_LANGUAGE, "attributes-natural-language", language);
+        request
+    }
+
+    pub fn group(&mut self, tag: u8) -> &mut Self {
+        self.buf.push(tag);
+        self
+    }
+
+    fn value(&mut self, tag: u8, name: &str, value: &[u8]) {
+        self.buf.push(tag);
+        self.buf.extend_from_slice(&(name.len().min(u16::MAX as usize) as u16).to_be_bytes());
+        self.buf.extend_from_slice(&name.as_bytes()[..name.len().min(u16::MAX as usize)]);
+        self.buf.extend_from_slice(&(value.len().min(u16::MAX as usize) as u16).to_be_bytes());
+        self.buf.extend_from_slice(&value[..value.len().min(u16::MAX as usize)]);
+    }
+
+    pub fn string(&mut self, tag: u8, name: &str, value: &str) -> &mut Self {
+        self.value(tag, name, value.as_bytes());
+        self
+    }
+
+    /// A multi-valued attribute: later values go out with an empty name.
+    pub fn strings(&mut self, tag: u8, name: &str, values: &[&str]) -> &mut Self {
+        for (i, value) in values.iter().enumerate() {
+            self.value(tag, if i == 0 { name } else { "" }, value.as_bytes());
+        }
+        self
+    }
+
+    pub fn integer(&mut self, name: &str, value: i32) -> &mut Self {
+        self.value(IPP_TAG_INTEGER, name, &value.to_be_bytes());
+        self
+    }
+
+    pub fn enumeration(&mut self, name: &str, value: i32) -> &mut Self {
+        self.value(IPP_TAG_ENUM, name, &value.to_be_bytes());
+        self
+    }
+
+    pub fn boolean(&mut self, name: &str, value: bool) -> &mut Self {
+        self.value(IPP_TAG_BOOLEAN, name, &[value as u8]);
+        self
+    }
+
+    pub fn finish(mut self, data: &[u8]) -> Vec<u8> {
+        self.buf.push(IPP_TAG_END);
+        self.buf.extend_from_slice(data);
+        self.buf
+    }
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be_i32(data: &[u8], at: usize) -> Option<i32> {
+    Some(i32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn decode_value(tag: u8, data: &[u8]) -> IppValue {
+    match (tag, data.len()) {
+        (IPP_TAG_INTEGER, 4) => IppValue::Integer(be_i32(data, 0).unwrap_or(0)),
+        (IPP_TAG_ENUM, 4) => IppValue::Enum(be_i32(data, 0).unwrap_or(0)),
+        (IPP_TAG_BOOLEAN, 1) => IppValue::Boolean(data[0] != 0),
+        (IPP_TAG_RESOLUTION, 9) => IppValue::Resolution {
+            x: be_i32(data, 0).unwrap_or(0),
+            y: be_i32(data, 4).unwrap_or(0),
+            units: data[8],
+        },
+        (IPP_TAG_RANGE, 8) => IppValue::Range(be_i32(data, 0).unwrap_or(0), be_i32(data, 4).unwrap_or(0)),
+        (0x41..=0x49, _) => IppValue::Text(tag, String::from_utf8_lossy(data).into_owned()),
+        _ => IppValue::Other(tag, data.to_vec()),
+    }
+}
+
+/// Parse a message. `None` when the header is short, a value runs past the
+/// end, an additional value has no attribute to belong to, or the end tag
+/// is missing.
+pub fn parse_ipp_message(msg: &[u8]) -> Option<IppMessage> {
+    let version = (*msg.first()?, *msg.get(1)?);
+    let code = be_u16(msg, 2)?;
+    let request_id = be_i32(msg, 4)? as u32;
+    let mut attributes: Vec<IppAttribute> = Vec::new();
+    let mut group = 0u8;
+    // Nesting depth inside a collection value; member attributes are skipped.
+    let mut depth = 0usize;
+    let mut pos = 8usize;
+    loop {
+        let tag = *msg.get(pos)?;
+        pos += 1;
+        if tag < 0x10 {
+            if depth > 0 {
+                return None;
+            }
+            if tag == IPP_TAG_END {
+                break;
+            }
+            group = tag;
+            continue;
+        }
+        let name_len = be_u16(msg, pos)? as usize;
+        let name = msg.get(pos + 2..pos + 2 + name_len)?;
+        pos += 2 + name_len;
+        let value_len = be_u16(msg, pos)? as usize;
+        let data = msg.get(pos + 2..pos + 2 + value_len)?;
+        pos += 2 + value_len;
+
+        match tag {
+            IPP_TAG_BEGIN_COLLECTION => {
+                depth += 1;
+                if depth > 1 {
+                    continue;
+                }
+            }
+            IPP_TAG_END_COLLECTION => {
+                depth = depth.checked_sub(1)?;
+                continue;
+            }
+            _ if depth > 0 => continue,
+            _ => {}
+        }
+        let value = if tag == IPP_TAG_BEGIN_COLLECTION {
+            IppValue::Collection
+        } else {
+            decode_value(tag, data)
+        };
+        if name.is_empty() {
+            attributes.last_mut()?.values.push(value);
+        } else {
+            if group == 0 {
+                return None;
+            }
+            attributes.push(IppAttribute {
+                group,
+                name: String::from_utf8_lossy(name).into_owned(),
+                values: alloc::vec![value],
+            });
+        }
+    }
+    Some(IppMessage {
+        version,
+        code,
+        request_id,
+        attributes,
+        data_offset: pos,
+    })
+}
+
+/// RFC 8011 status code keyword, or "unknown".
+pub fn ipp_status_text(code: u16) -> &'static str {
+    match code {
+        0x0000 => "successful-ok",
+        0x0001 => "successful-ok-ignored-or-substituted-attributes",
+        0x0002 => "successful-ok-conflicting-attributes",
+        0x0400 => "client-error-bad-request",
+        0x0401 => "client-error-forbidden",
+        0x0402 => "client-error-not-authenticated",
+        0x0403 => "client-error-not-authorized",
+        0x0404 => "client-error-not-possible",
+        0x0405 => "client-error-timeout",
+        0x0406 => "client-error-not-found",
+        0x0407 => "client-error-gone",
+        0x0408 => "client-error-request-entity-too-large",
+        0x040A => "client-error-document-format-not-supported",
+        0x040B => "client-error-attributes-or-values-not-supported",
+        0x040C => "client-error-uri-scheme-not-supported",
+        0x040E => "client-error-conflicting-attributes",
+        0x0411 => "client-error-document-format-error",
+        0x0500 => "server-error-internal-error",
+        0x0501 => "server-error-operation-not-supported",
+        0x0502 => "server-error-service-unavailable",
+        0x0503 => "server-error-version-not-supported",
+        0x0504 => "server-error-device-error",
+        0x0505 => "server-error-temporary-error",
+        0x0506 => "server-error-not-accepting-jobs",
+        0x0507 => "server-error-busy",
+        0x0508 => "server-error-job-canceled",
+        _ => "unknown",
+    }
+}
+
+/// RFC 8011 `job-state` keyword.
+pub fn ipp_job_state_text(state: i32) -> &'static str {
+    match state {
+        IPP_JOB_PENDING => "pending",
+        IPP_JOB_HELD => "pending-held",
+        IPP_JOB_PROCESSING => "processing",
+        IPP_JOB_STOPPED => "processing-stopped",
+        IPP_JOB_CANCELED => "canceled",
+        IPP_JOB_ABORTED => "aborted",
+        IPP_JOB_COMPLETED => "completed",
+        _ => "unknown",
+    }
+}
+
+/// `ipp://host[:port]/path` (or `ipps://`) as the `http(s)://` URL the
+/// request is POSTed to; IPP's default port is 631.
+pub fn ipp_http_url(uri: &str) -> Option<String> {
+    let (scheme, rest) = uri.split_once("://")?;
+    let (http, default_port) = match scheme.to_ascii_lowercase().as_str() {
+        "ipp" => ("http", ":631"),
+        "ipps" => ("https", ":631"),
+        "http" => ("http", ""),
+        "https" => ("https", ""),
+        _ => return None,
+    };
+    let authority_len = rest.find('/').unwrap_or(rest.len());
+    let (authority, path) = rest.split_at(authority_len);
+    if authority.is_empty() || authority.bytes().any(|b| b <= b' ') {
+        return None;
+    }
+    let has_port = match authority.rfind(':') {
+        Some(idx) => !authority[idx..].contains(']'),
+        None => false,
+    };
+    let port = if has_port { "" } else { default_port };
+    let path = if path.is_empty() { "/" } else { path };
+    Some(alloc::format!("{}://{}{}{}", http, authority, port, path))
+}
@@ -0,0 +1,54 @@
+//! Network parsers used by the kernel HTTP, Gemini, Gopher, FTP, mail
+//! (IMAP/SMTP), DHCP and IPP printing clients (with mDNS discovery and PWG
+//! raster pages), the ARP/NDP frames of the neighbor table and TCP MSS
+//! clamping.
+//!
+//! Everything in here handles bytes that come straight from a remote server,
+//! so it lives outside the kernel: the crate is `no_std` + `alloc` for the
+//! UEFI build and also compiles on the host, where `cargo test` runs the
+//! property tests in `tests/` and `fuzz/` holds the cargo-fuzz targets.
+//!
+//! Parsers never panic on malformed input; they return `None` (or `false`)
+//! and the caller drops the response.
+//!
+//! ```text
+//! make test-netparse                          # cargo test on the host
+//! make fuzz-netparse FUZZ_TARGET=chunked      # cargo +nightly fuzz run chunked
+//! ```
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod cookie;
+pub mod dhcp;
+pub mod dns;
+pub mod ftp;
+pub mod gemini;
+pub mod gopher;
+pub mod hpack;
+pub mod http;
+pub mod imap;
+pub mod ipp;
+pub mod mime;
+pub mod mss;
+pub mod neighbor;
+pub mod pwg;
+pub mod smtp;
+pub mod url;
+
+use alloc::string::String;
+
+pub fn ascii_lowercase(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for b in text.bytes() {
+        out.push((b.to_ascii_lowercase()) as char);
+    }
+    out
+}
+
+pub fn starts_with_ignore_ascii_case(text: &str, prefix: &str) -> bool {
+    text.get(..prefix.len())
+        .map(|head| head.eq_ignore_ascii_case(prefix))
+        .unwrap_or(false)
+}
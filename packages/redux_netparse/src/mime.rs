@@ -0,0 +1,513 @@
+//! Internet message helpers for the mail client: RFC 5322 headers, MIME
+//! bodies (RFC 2045/2046), encoded words (RFC 2047), base64,
+//! quoted-printable and the `Date:` format.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{ascii_lowercase, starts_with_ignore_ascii_case};
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+/// Nested multiparts deeper than this are not searched for a text part.
+const MIME_MAX_DEPTH: usize = 8;
+
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        out.push(BASE64_ALPHABET[(n >> 18) as usize & 63] as char);
+        out.push(BASE64_ALPHABET[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 { BASE64_ALPHABET[(n >> 6) as usize & 63] as char } else { '=' });
+        out.push(if chunk.len() > 2 { BASE64_ALPHABET[n as usize & 63] as char } else { '=' });
+    }
+    out
+}
+
+fn base64_value(b: u8) -> Option<u32> {
+    match b {
+        b'A'..=b'Z' => Some((b - b'A') as u32),
+        b'a'..=b'z' => Some((b - b'a') as u32 + 26),
+        b'0'..=b'9' => Some((b - b'0') as u32 + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+/// Decode base64, skipping whitespace (bodies are wrapped at 76 columns).
+/// Padding is optional; any other stray byte fails the decode.
+pub fn base64_decode(text: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let mut padding = false;
+    for &b in text {
+        if b.is_ascii_whitespace() {
+            continue;
+        }
+        if b == b'=' {
+            padding = true;
+            continue;
+        }
+        if padding {
+            return None;
+        }
+        acc = (acc << 6) | base64_value(b)?;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Decode a quoted-printable body. `=` at the end of a line is a soft break;
+/// a malformed `=XX` is kept as is.
+pub fn quoted_printable_decode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0usize;
+    while i < data.len() {
+        let b = data[i];
+        if b != b'=' {
+            out.push(b);
+            i += 1;
+            continue;
+        }
+        match (data.get(i + 1), data.get(i + 2)) {
+            (Some(b'\r'), Some(b'\n')) => i += 3,
+            (Some(b'\n'), _) => i += 2,
+            (Some(&h), Some(&l)) => match (hex_value(h), hex_value(l)) {
+                (Some(h), Some(l)) => {
+                    out.push((h << 4) | l);
+                    i += 3;
+                }
+                _ => {
+                    out.push(b'=');
+                    i += 1;
+                }
+            },
+            _ => {
+                out.push(b'=');
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Bytes in `charset` as text. UTF-8 and ASCII go through a lossy UTF-8
+/// decode; Latin-1 and Windows-1252 map byte-for-byte (close enough for the
+/// 0xA0..0xFF range that carries the accented letters).
+pub fn decode_charset(data: &[u8], charset: &str) -> String {
+    let charset = ascii_lowercase(charset.trim().trim_matches('"'));
+    match charset.as_str() {
+        "iso-8859-1" | "iso-8859-15" | "latin1" | "windows-1252" | "cp1252" => {
+            data.iter().map(|&b| b as char).collect()
+        }
+        _ => String::from_utf8_lossy(data).into_owned(),
+    }
+}
+
+fn decode_encoded_word(word: &str) -> Option<String> {
+    // =?charset?B|Q?text?=
+    let inner = word.strip_prefix("=?")?.strip_suffix("?=")?;
+    let mut parts = inner.splitn(3, '?');
+    let charset = parts.next()?;
+    let encoding = parts.next()?;
+    let text = parts.next()?;
+    // RFC 2231 language suffix: charset*lang
+    let charset = charset.split('*').next().unwrap_or(charset);
+    let bytes = if encoding.eq_ignore_ascii_case("B") {
+        base64_decode(text.as_bytes())?
+    } else if encoding.eq_ignore_ascii_case("Q") {
+        let spaced: Vec<u8> = text.bytes().map(|b| if b == b'_' { b' ' } else { b }).collect();
+        quoted_printable_decode(spaced.as_slice())
+    } else {
+        return None;
+    };
+    Some(decode_charset(bytes.as_slice(), charset))
+}
+
+/// Decode RFC 2047 encoded words in a header value. Whitespace between two
+/// adjacent encoded words is dropped, as the RFC requires.
+pub fn decode_encoded_words(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut pending_space = String::new();
+    let mut last_was_word = false;
+    while !rest.is_empty() {
+        let Some(start) = rest.find("=?") else {
+            out.push_str(pending_space.as_str());
+            out.push_str(rest);
+            return out;
+        };
+        let (before, candidate) = rest.split_at(start);
+        // The word ends at the "?=" after charset and encoding.
+        let end = candidate
+            .char_indices()
+            .skip(2)
+            .filter(|&(_, c)| c == '?')
+            .nth(2)
+            .filter(|&(i, _)| candidate[i..].starts_with("?="))
+            .map(|(i, _)| i + 2);
+        let decoded = end.and_then(|end| decode_encoded_word(&candidate[..end]).map(|d| (d, end)));
+        match decoded {
+            Some((decoded, end)) => {
+                if !(last_was_word && before.trim().is_empty()) {
+                    out.push_str(pending_space.as_str());
+                    out.push_str(before);
+                }
+                pending_space.clear();
+                out.push_str(decoded.as_str());
+                last_was_word = true;
+                rest = &candidate[end..];
+                let ws = rest.len() - rest.trim_start().len();
+                pending_space.push_str(&rest[..ws]);
+                rest = &rest[ws..];
+            }
+            None => {
+                out.push_str(pending_space.as_str());
+                pending_space.clear();
+                out.push_str(before);
+                out.push_str("=?");
+                last_was_word = false;
+                rest = &candidate[2..];
+            }
+        }
+    }
+    out.push_str(pending_space.as_str());
+    out
+}
+
+/// Encode a header value as one UTF-8 `B` word when it is not plain ASCII.
+pub fn encode_header_word(text: &str) -> String {
+    if text.bytes().all(|b| (b' '..=b'~').contains(&b)) {
+        return String::from(text);
+    }
+    alloc::format!("=?UTF-8?B?{}?=", base64_encode(text.as_bytes()))
+}
+
+fn find_header_end(raw: &[u8]) -> Option<(usize, usize)> {
+    let mut i = 0usize;
+    while i < raw.len() {
+        if raw[i] == b'\n' {
+            if raw.get(i + 1) == Some(&b'\n') {
+                return Some((i + 1, i + 2));
+            }
+            if raw.get(i + 1) == Some(&b'\r') && raw.get(i + 2) == Some(&b'\n') {
+                return Some((i + 1, i + 3));
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Unfolded header fields and the offset where the body starts. A message
+/// without a blank line is all headers.
+pub fn parse_headers(raw: &[u8]) -> (Vec<(String, String)>, usize) {
+    let (head_end, body_offset) = find_header_end(raw).unwrap_or((raw.len(), raw.len()));
+    let head = String::from_utf8_lossy(&raw[..head_end]);
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in head.split('\n') {
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((String::from(name.trim()), String::from(value.trim())));
+        }
+    }
+    (headers, body_offset)
+}
+
+pub fn header_value<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// Lowercase media type of a `Content-Type` value (`text/plain` by default).
+pub fn content_type_mime(value: Option<&str>) -> String {
+    match value.and_then(|v| v.split(';').next()).map(str::trim) {
+        Some(mime) if !mime.is_empty() => ascii_lowercase(mime),
+        _ => String::from("text/plain"),
+    }
+}
+
+/// A parameter of a structured header such as `Content-Type`, unquoted.
+pub fn header_param(value: &str, name: &str) -> Option<String> {
+    for part in value.split(';').skip(1) {
+        let Some((key, val)) = part.split_once('=') else {
+            continue;
+        };
+        if key.trim().eq_ignore_ascii_case(name) {
+            let val = val.trim();
+            let val = val.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(val);
+            return Some(String::from(val));
+        }
+    }
+    None
+}
+
+fn decode_transfer(body: &[u8], encoding: Option<&str>) -> Vec<u8> {
+    let encoding = encoding.map(|e| ascii_lowercase(e.trim())).unwrap_or_default();
+    match encoding.as_str() {
+        "base64" => base64_decode(body).unwrap_or_else(|| body.to_vec()),
+        "quoted-printable" => quoted_printable_decode(body),
+        _ => body.to_vec(),
+    }
+}
+
+/// Split a multipart body on `--boundary` lines; the preamble and epilogue
+/// are dropped.
+pub fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = alloc::format!("--{}", boundary);
+    let delimiter = delimiter.as_bytes();
+    let mut parts = Vec::new();
+    let mut part_start: Option<usize> = None;
+    let mut line_start = 0usize;
+    while line_start < body.len() {
+        let line_end = body[line_start..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map(|p| line_start + p + 1)
+            .unwrap_or(body.len());
+        let line = &body[line_start..line_end];
+        if line.starts_with(delimiter) {
+            if let Some(start) = part_start {
+                // The CRLF before the delimiter belongs to the delimiter.
+                let mut end = line_start;
+                if end > start && body[end - 1] == b'\n' {
+                    end -= 1;
+                    if end > start && body[end - 1] == b'\r' {
+                        end -= 1;
+                    }
+                }
+                parts.push(&body[start..end]);
+            }
+            if line[delimiter.len()..].starts_with(b"--") {
+                return parts;
+            }
+            part_start = Some(line_end);
+        }
+        line_start = line_end;
+    }
+    parts
+}
+
+fn strip_html(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut in_tag = false;
+    let mut tag = String::new();
+    for c in html.chars() {
+        if in_tag {
+            if c == '>' {
+                in_tag = false;
+                let name = ascii_lowercase(tag.trim_start_matches('/').split_whitespace().next().unwrap_or(""));
+                // Line breaks always count; block boundaries only end a
+                // non-empty line.
+                if matches!(name.as_str(), "br" | "br/")
+                    || (matches!(name.as_str(), "p" | "div" | "tr" | "li" | "h1" | "h2" | "h3")
+                        && !out.is_empty()
+                        && !out.ends_with('\n'))
+                {
+                    out.push('\n');
+                }
+                tag.clear();
+            } else {
+                tag.push(c);
+            }
+        } else if c == '<' {
+            in_tag = true;
+        } else {
+            out.push(c);
+        }
+    }
+    out.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Text of one MIME entity: `text/plain` wins, `text/html` is stripped of
+/// tags as a fallback, and multiparts are searched depth-first.
+fn entity_text(raw: &[u8], depth: usize) -> Option<(String, bool)> {
+    let (headers, offset) = parse_headers(raw);
+    let body = raw.get(offset..).unwrap_or(&[]);
+    let content_type = header_value(&headers, "Content-Type");
+    let mime = content_type_mime(content_type);
+
+    if mime.starts_with("multipart/") {
+        if depth >= MIME_MAX_DEPTH {
+            return None;
+        }
+        let boundary = content_type.and_then(|v| header_param(v, "boundary"))?;
+        let mut html = None;
+        for part in split_multipart(body, boundary.as_str()) {
+            match entity_text(part, depth + 1) {
+                Some((text, false)) => return Some((text, false)),
+                Some((text, true)) if html.is_none() => html = Some(text),
+                _ => {}
+            }
+        }
+        return html.map(|text| (text, true));
+    }
+    if mime == "message/rfc822" && depth < MIME_MAX_DEPTH {
+        return entity_text(body, depth + 1);
+    }
+    if !mime.starts_with("text/") {
+        return None;
+    }
+
+    let disposition = header_value(&headers, "Content-Disposition").unwrap_or("");
+    if starts_with_ignore_ascii_case(disposition.trim(), "attachment") {
+        return None;
+    }
+    let decoded = decode_transfer(body, header_value(&headers, "Content-Transfer-Encoding"));
+    let charset = content_type.and_then(|v| header_param(v, "charset")).unwrap_or_default();
+    let text = decode_charset(decoded.as_slice(), charset.as_str());
+    if mime == "text/html" {
+        Some((strip_html(text.as_str()), true))
+    } else {
+        Some((text, false))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MailMessage {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub date: String,
+    /// Readable text of the message with `\n` line ends.
+    pub text: String,
+}
+
+/// Parse a whole RFC 5322 message (or just its header block) for display.
+pub fn parse_message(raw: &[u8]) -> MailMessage {
+    let (headers, _) = parse_headers(raw);
+    let field = |name: &str| decode_encoded_words(header_value(&headers, name).unwrap_or(""));
+    let text = entity_text(raw, 0)
+        .map(|(text, _)| text)
+        .unwrap_or_else(|| String::from("(sin parte de texto)"));
+    MailMessage {
+        from: field("From"),
+        to: field("To"),
+        subject: field("Subject"),
+        date: field("Date"),
+        text: text.replace("\r\n", "\n"),
+    }
+}
+
+/// Bare address out of `Name <user@host>` or `user@host`.
+pub fn extract_address(text: &str) -> Option<String> {
+    let text = text.trim();
+    let addr = match (text.rfind('<'), text.rfind('>')) {
+        (Some(open), Some(close)) if open < close => &text[open + 1..close],
+        _ => text,
+    };
+    let addr = addr.trim();
+    let (local, domain) = addr.split_once('@')?;
+    let ok = !local.is_empty()
+        && !domain.is_empty()
+        && !domain.contains('@')
+        && addr.bytes().all(|b| b > b' ' && b < 0x7F && !b"<>(),;:\\\"[]".contains(&b));
+    if ok {
+        Some(String::from(addr))
+    } else {
+        None
+    }
+}
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+
+/// `Date:` value (RFC 5322 section 3.3) for a UTC timestamp shown in the
+/// given zone, e.g. `Tue, 15 Oct 2024 09:30:00 +0200`.
+pub fn format_date(unix_seconds: i64, offset_minutes: i32) -> String {
+    let local = unix_seconds + offset_minutes as i64 * 60;
+    let days = local.div_euclid(86_400);
+    let secs = local.rem_euclid(86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    let sign = if offset_minutes < 0 { '-' } else { '+' };
+    let offset = offset_minutes.unsigned_abs();
+    alloc::format!(
+        "{}, {} {} {} {:02}:{:02}:{:02} {}{:02}{:02}",
+        WEEKDAYS[days.rem_euclid(7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        sign,
+        offset / 60,
+        offset % 60
+    )
+}
+
+/// A plain-text message ready for SMTP `DATA` (before dot-stuffing). The
+/// body keeps its text as UTF-8 and every line ends in CRLF.
+pub fn compose_message(from: &str, to: &str, subject: &str, date: &str, message_id: &str, body: &str) -> String {
+    let ascii_body = body.is_ascii();
+    let mut out = String::new();
+    for (name, value) in [
+        ("From", String::from(from)),
+        ("To", String::from(to)),
+        ("Subject", encode_header_word(subject)),
+        ("Date", String::from(date)),
+        ("Message-ID", alloc::format!("<{}>", message_id)),
+        ("MIME-Version", String::from("1.0")),
+        ("Content-Type", String::from("text/plain; charset=utf-8")),
+        (
+            "Content-Transfer-Encoding",
+            String::from(if ascii_body { "7bit" } else { "8bit" }),
+        ),
+    ] {
+        out.push_str(name);
+        out.push_str(": ");
+        // A line break in a value would start a new header.
+        out.push_str(value.replace(['\r', '\n'], " ").as_str());
+        out.push_str("\r\n");
+    }
+    out.push_str("\r\n");
+    for line in body.split('\n') {
+        out.push_str(line.trim_end_matches('\r'));
+        out.push_str("\r\n");
+    }
+    out
+}
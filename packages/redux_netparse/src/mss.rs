@@ -0,0 +1,100 @@
+//! TCP MSS clamping on outgoing Ethernet frames. With a jumbo MTU the stack
+//! advertises an MSS that only the local segment can carry; SYNs headed past
+//! the gateway get it lowered to what a standard path takes, the way routers
+//! clamp it.
+
+const ETH_HEADER_LEN: usize = 14;
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const IP_PROTO_TCP: u8 = 6;
+const TCP_FLAG_SYN: u8 = 0x02;
+const TCP_OPT_END: u8 = 0;
+const TCP_OPT_NOP: u8 = 1;
+const TCP_OPT_MSS: u8 = 2;
+
+/// MSS for a path MTU: the MTU minus the IPv4 and TCP headers.
+pub fn mss_for_mtu(mtu: usize) -> u16 {
+    mtu.saturating_sub(40).min(u16::MAX as usize) as u16
+}
+
+/// Offsets of the IPv4 header and TCP segment of a SYN, and the segment
+/// length. `None` for anything else (including fragments).
+fn ipv4_tcp_syn(frame: &[u8]) -> Option<(usize, usize, usize)> {
+    if u16::from_be_bytes([*frame.get(12)?, *frame.get(13)?]) != ETHERTYPE_IPV4 {
+        return None;
+    }
+    let ip = frame.get(ETH_HEADER_LEN..)?;
+    let ihl = (*ip.first()? as usize & 0x0f) * 4;
+    let total_len = u16::from_be_bytes([*ip.get(2)?, *ip.get(3)?]) as usize;
+    let fragmented = u16::from_be_bytes([*ip.get(6)?, *ip.get(7)?]) & 0x3fff != 0;
+    if ihl < 20 || total_len < ihl + 20 || total_len > ip.len() || fragmented || *ip.get(9)? != IP_PROTO_TCP {
+        return None;
+    }
+    let tcp = &ip[ihl..total_len];
+    if tcp[13] & TCP_FLAG_SYN == 0 {
+        return None;
+    }
+    Some((ETH_HEADER_LEN, ETH_HEADER_LEN + ihl, total_len - ihl))
+}
+
+/// Destination of an IPv4 TCP SYN frame.
+pub fn ipv4_tcp_syn_destination(frame: &[u8]) -> Option<[u8; 4]> {
+    let (ip, _, _) = ipv4_tcp_syn(frame)?;
+    frame.get(ip + 16..ip + 20)?.try_into().ok()
+}
+
+fn checksum_add(mut sum: u32, data: &[u8]) -> u32 {
+    let mut chunks = data.chunks_exact(2);
+    for pair in &mut chunks {
+        sum = sum.wrapping_add(u16::from_be_bytes([pair[0], pair[1]]) as u32);
+    }
+    if let [last] = chunks.remainder() {
+        sum = sum.wrapping_add((*last as u32) << 8);
+    }
+    sum
+}
+
+fn fold_checksum(mut sum: u32) -> u16 {
+    while (sum >> 16) != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum as u16
+}
+
+/// Lower the MSS option of an IPv4 TCP SYN to `max_mss` and recompute the
+/// TCP checksum. Returns the MSS it replaced, `None` when the frame is not a
+/// SYN or already within the limit.
+pub fn clamp_tcp_mss(frame: &mut [u8], max_mss: u16) -> Option<u16> {
+    let (ip, tcp, tcp_len) = ipv4_tcp_syn(frame)?;
+    let data_offset = (frame[tcp + 12] >> 4) as usize * 4;
+    if data_offset < 20 || data_offset > tcp_len {
+        return None;
+    }
+    let mut at = tcp + 20;
+    let end = tcp + data_offset;
+    while at < end {
+        match frame[at] {
+            TCP_OPT_END => return None,
+            TCP_OPT_NOP => at += 1,
+            kind => {
+                let len = *frame.get(at + 1)? as usize;
+                if len < 2 || at + len > end {
+                    return None;
+                }
+                if kind == TCP_OPT_MSS && len == 4 {
+                    let mss = u16::from_be_bytes([frame[at + 2], frame[at + 3]]);
+                    if mss <= max_mss {
+                        return None;
+                    }
+                    frame[at + 2..at + 4].copy_from_slice(&max_mss.to_be_bytes());
+                    frame[tcp + 16..tcp + 18].copy_from_slice(&[0, 0]);
+                    let pseudo = checksum_add(0, &frame[ip + 12..ip + 20]) + IP_PROTO_TCP as u32 + tcp_len as u32;
+                    let csum = !fold_checksum(checksum_add(pseudo, &frame[tcp..tcp + tcp_len]));
+                    frame[tcp + 16..tcp + 18].copy_from_slice(&csum.to_be_bytes());
+                    return Some(mss);
+                }
+                at += len;
+            }
+        }
+    }
+    None
+}
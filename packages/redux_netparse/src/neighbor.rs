@@ -0,0 +1,196 @@
+//! ARP (RFC 826) and IPv6 neighbor discovery (RFC 4861) frames: what a
+//! received Ethernet frame says about a neighbor's link-layer address, and
+//! the ARP frames the kernel sends or feeds to its own stack.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub const ETHERTYPE_IPV4: u16 = 0x0800;
+pub const ETHERTYPE_ARP: u16 = 0x0806;
+pub const ETHERTYPE_IPV6: u16 = 0x86dd;
+pub const ARP_OP_REQUEST: u16 = 1;
+pub const ARP_OP_REPLY: u16 = 2;
+/// Ethernet header plus an Ethernet/IPv4 ARP body.
+pub const ARP_FRAME_LEN: usize = 42;
+pub const BROADCAST_MAC: [u8; 6] = [0xff; 6];
+
+const ETH_HEADER_LEN: usize = 14;
+const IPV6_HEADER_LEN: usize = 40;
+const IPV6_NEXT_HEADER_ICMPV6: u8 = 58;
+const ICMPV6_NEIGHBOR_SOLICIT: u8 = 135;
+const ICMPV6_NEIGHBOR_ADVERT: u8 = 136;
+const NDP_OPT_SOURCE_LL: u8 = 1;
+const NDP_OPT_TARGET_LL: u8 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NeighborIp {
+    V4([u8; 4]),
+    V6([u8; 16]),
+}
+
+impl NeighborIp {
+    /// Dotted quad, or the uncompressed colon form for IPv6.
+    pub fn text(&self) -> String {
+        match self {
+            NeighborIp::V4(ip) => alloc::format!("{}.{}.{}.{}", ip[0], ip[1], ip[2], ip[3]),
+            NeighborIp::V6(ip) => {
+                let groups: Vec<String> = ip
+                    .chunks(2)
+                    .map(|pair| alloc::format!("{:x}", u16::from_be_bytes([pair[0], pair[1]])))
+                    .collect();
+                groups.join(":")
+            }
+        }
+    }
+}
+
+/// How a neighbor made itself known.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NeighborSource {
+    Arp,
+    Ndp,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NeighborObservation {
+    pub ip: NeighborIp,
+    pub mac: [u8; 6],
+    pub source: NeighborSource,
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*data.get(at)?, *data.get(at + 1)?]))
+}
+
+fn mac_at(data: &[u8], at: usize) -> Option<[u8; 6]> {
+    data.get(at..at + 6)?.try_into().ok()
+}
+
+fn usable_mac(mac: &[u8; 6]) -> bool {
+    // Group (multicast/broadcast) bit clear and not all zeroes.
+    mac[0] & 1 == 0 && mac.iter().any(|&b| b != 0)
+}
+
+fn observe_arp(body: &[u8]) -> Option<NeighborObservation> {
+    // Only Ethernet hardware (1) with IPv4 protocol addresses.
+    if be_u16(body, 0)? != 1 || be_u16(body, 2)? != ETHERTYPE_IPV4 || body.get(4..6)? != [6, 4] {
+        return None;
+    }
+    let op = be_u16(body, 6)?;
+    if op != ARP_OP_REQUEST && op != ARP_OP_REPLY {
+        return None;
+    }
+    let mac = mac_at(body, 8)?;
+    let ip: [u8; 4] = body.get(14..18)?.try_into().ok()?;
+    if !usable_mac(&mac) || ip == [0; 4] {
+        return None;
+    }
+    Some(NeighborObservation {
+        ip: NeighborIp::V4(ip),
+        mac,
+        source: NeighborSource::Arp,
+    })
+}
+
+fn observe_ndp(packet: &[u8]) -> Option<NeighborObservation> {
+    if packet.get(6)? != &IPV6_NEXT_HEADER_ICMPV6 {
+        return None;
+    }
+    let icmp = packet.get(IPV6_HEADER_LEN..)?;
+    let (ip, wanted_option): ([u8; 16], u8) = match *icmp.first()? {
+        // A solicitation vouches for its sender; the unspecified source of
+        // duplicate address detection carries no option and is skipped.
+        ICMPV6_NEIGHBOR_SOLICIT => (packet.get(8..24)?.try_into().ok()?, NDP_OPT_SOURCE_LL),
+        ICMPV6_NEIGHBOR_ADVERT => (icmp.get(8..24)?.try_into().ok()?, NDP_OPT_TARGET_LL),
+        _ => return None,
+    };
+    let mut options = icmp.get(24..)?;
+    while options.len() >= 2 {
+        let kind = options[0];
+        let len = options[1] as usize * 8;
+        if len == 0 || len > options.len() {
+            return None;
+        }
+        if kind == wanted_option && len >= 8 {
+            let mac = mac_at(options, 2)?;
+            if !usable_mac(&mac) || ip == [0; 16] {
+                return None;
+            }
+            return Some(NeighborObservation {
+                ip: NeighborIp::V6(ip),
+                mac,
+                source: NeighborSource::Ndp,
+            });
+        }
+        options = &options[len..];
+    }
+    None
+}
+
+/// The neighbor an Ethernet frame vouches for: the sender of an ARP
+/// request/reply, or the link-layer option of an NDP solicitation or
+/// advertisement. `None` for every other frame.
+pub fn observe_frame(frame: &[u8]) -> Option<NeighborObservation> {
+    let payload = frame.get(ETH_HEADER_LEN..)?;
+    match be_u16(frame, 12)? {
+        ETHERTYPE_ARP => observe_arp(payload),
+        ETHERTYPE_IPV6 => observe_ndp(payload),
+        _ => None,
+    }
+}
+
+/// One Ethernet/IPv4 ARP frame.
+pub fn arp_frame(
+    op: u16,
+    eth_dst: [u8; 6],
+    sender_mac: [u8; 6],
+    sender_ip: [u8; 4],
+    target_mac: [u8; 6],
+    target_ip: [u8; 4],
+) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(ARP_FRAME_LEN);
+    frame.extend_from_slice(&eth_dst);
+    frame.extend_from_slice(&sender_mac);
+    frame.extend_from_slice(&ETHERTYPE_ARP.to_be_bytes());
+    frame.extend_from_slice(&1u16.to_be_bytes());
+    frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+    frame.extend_from_slice(&[6, 4]);
+    frame.extend_from_slice(&op.to_be_bytes());
+    frame.extend_from_slice(&sender_mac);
+    frame.extend_from_slice(&sender_ip);
+    frame.extend_from_slice(&target_mac);
+    frame.extend_from_slice(&target_ip);
+    frame
+}
+
+/// Gratuitous ARP announcing `ip` at `mac` (RFC 5227 announcement: a
+/// broadcast request with sender and target address both set to `ip`).
+pub fn gratuitous_arp(mac: [u8; 6], ip: [u8; 4]) -> Vec<u8> {
+    arp_frame(ARP_OP_REQUEST, BROADCAST_MAC, mac, ip, [0; 6], ip)
+}
+
+/// `aa:bb:cc:dd:ee:ff`, also accepting `-` as separator.
+pub fn parse_mac(text: &str) -> Option<[u8; 6]> {
+    let mut out = [0u8; 6];
+    let mut parts = text.trim().split([':', '-']);
+    for byte in out.iter_mut() {
+        let part = parts.next()?;
+        if part.is_empty() || part.len() > 2 {
+            return None;
+        }
+        *byte = u8::from_str_radix(part, 16).ok()?;
+    }
+    parts.next().is_none().then_some(out)
+}
+
+pub fn mac_text(mac: &[u8; 6]) -> String {
+    alloc::format!(
+        "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+        mac[0],
+        mac[1],
+        mac[2],
+        mac[3],
+        mac[4],
+        mac[5]
+    )
+}
@@ -0,0 +1,237 @@
+//! PWG Raster (PWG 5102.4), the page format every IPP Everywhere printer
+//! accepts: a `RaS2` sync word, then per page a 1796-byte big-endian header
+//! and the compressed lines.
+//!
+//! Each line starts with a repeat count (how many more identical lines
+//! follow), then runs of pixels: a control byte 0..=127 repeats the next
+//! pixel 1..=128 times, 129..=255 announces 257 - n literal pixels.
+
+use alloc::vec::Vec;
+
+pub const PWG_SYNC: &[u8; 4] = b"RaS2";
+pub const PWG_HEADER_LEN: usize = 1796;
+pub const PWG_COLOR_SPACE_SGRAY: u32 = 18;
+pub const PWG_COLOR_SPACE_SRGB: u32 = 19;
+
+const OFF_MEDIA_CLASS: usize = 0;
+const OFF_HW_RESOLUTION: usize = 276;
+const OFF_NUM_COPIES: usize = 340;
+const OFF_PAGE_SIZE: usize = 352;
+const OFF_WIDTH: usize = 372;
+const OFF_HEIGHT: usize = 376;
+const OFF_BITS_PER_COLOR: usize = 384;
+const OFF_BITS_PER_PIXEL: usize = 388;
+const OFF_BYTES_PER_LINE: usize = 392;
+const OFF_COLOR_SPACE: usize = 400;
+const OFF_NUM_COLORS: usize = 420;
+const OFF_TOTAL_PAGE_COUNT: usize = 452;
+const OFF_PAGE_SIZE_NAME: usize = 1732;
+/// Largest page accepted when decoding (a 600 dpi A3 page is 7016 x 9921).
+const PWG_MAX_DIMENSION: u32 = 20_000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PwgPage {
+    pub width: u32,
+    pub height: u32,
+    pub dpi: u32,
+    /// sRGB 8-bit when set, sGray 8-bit otherwise.
+    pub color: bool,
+    pub copies: u32,
+    pub total_pages: u32,
+    /// Media size in points, e.g. 595 x 842 for A4.
+    pub page_size_pt: (u32, u32),
+    /// PWG self-describing media name, e.g. `iso_a4_210x297mm`.
+    pub media: &'static str,
+}
+
+impl PwgPage {
+    pub fn bytes_per_pixel(&self) -> usize {
+        if self.color {
+            3
+        } else {
+            1
+        }
+    }
+
+    pub fn bytes_per_line(&self) -> usize {
+        self.width as usize * self.bytes_per_pixel()
+    }
+}
+
+fn put_u32(header: &mut [u8], at: usize, value: u32) {
+    header[at..at + 4].copy_from_slice(&value.to_be_bytes());
+}
+
+fn put_str(header: &mut [u8], at: usize, value: &str) {
+    let len = value.len().min(63);
+    header[at..at + len].copy_from_slice(&value.as_bytes()[..len]);
+}
+
+fn get_u32(header: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(header.get(at..at + 4)?.try_into().ok()?))
+}
+
+pub fn encode_pwg_header(page: &PwgPage) -> Vec<u8> {
+    let mut header = alloc::vec![0u8; PWG_HEADER_LEN];
+    put_str(&mut header, OFF_MEDIA_CLASS, "PwgRaster");
+    put_u32(&mut header, OFF_HW_RESOLUTION, page.dpi);
+    put_u32(&mut header, OFF_HW_RESOLUTION + 4, page.dpi);
+    put_u32(&mut header, OFF_NUM_COPIES, page.copies);
+    put_u32(&mut header, OFF_PAGE_SIZE, page.page_size_pt.0);
+    put_u32(&mut header, OFF_PAGE_SIZE + 4, page.page_size_pt.1);
+    put_u32(&mut header, OFF_WIDTH, page.width);
+    put_u32(&mut header, OFF_HEIGHT, page.height);
+    put_u32(&mut header, OFF_BITS_PER_COLOR, 8);
+    put_u32(&mut header, OFF_BITS_PER_PIXEL, page.bytes_per_pixel() as u32 * 8);
+    put_u32(&mut header, OFF_BYTES_PER_LINE, page.bytes_per_line() as u32);
+    put_u32(
+        &mut header,
+        OFF_COLOR_SPACE,
+        if page.color { PWG_COLOR_SPACE_SRGB } else { PWG_COLOR_SPACE_SGRAY },
+    );
+    put_u32(&mut header, OFF_NUM_COLORS, page.bytes_per_pixel() as u32);
+    put_u32(&mut header, OFF_TOTAL_PAGE_COUNT, page.total_pages);
+    put_str(&mut header, OFF_PAGE_SIZE_NAME, page.media);
+    header
+}
+
+/// Append the pixel runs of one line (`bpp` bytes per pixel).
+fn encode_line(out: &mut Vec<u8>, line: &[u8], bpp: usize) {
+    let pixels = line.len() / bpp;
+    let pixel = |i: usize| &line[i * bpp..(i + 1) * bpp];
+    let mut i = 0usize;
+    while i < pixels {
+        let mut run = 1usize;
+        while i + run < pixels && run < 128 && pixel(i + run) == pixel(i) {
+            run += 1;
+        }
+        if run > 1 || i + 1 == pixels {
+            out.push((run - 1) as u8);
+            out.extend_from_slice(pixel(i));
+            i += run;
+            continue;
+        }
+        // Literal run up to the next pair of equal pixels.
+        let mut literal = 1usize;
+        while i + literal < pixels && literal < 128 {
+            let next = i + literal;
+            if next + 1 < pixels && pixel(next) == pixel(next + 1) {
+                break;
+            }
+            literal += 1;
+        }
+        if literal == 1 {
+            out.push(0);
+        } else {
+            out.push((257 - literal) as u8);
+        }
+        out.extend_from_slice(&line[i * bpp..(i + literal) * bpp]);
+        i += literal;
+    }
+}
+
+/// Encode one page (header and compressed lines). `row` fills line `y`
+/// with `bytes_per_line` bytes, so a page never has to exist whole in
+/// memory; identical consecutive lines are folded into one.
+pub fn encode_pwg_page(page: &PwgPage, row: &mut dyn FnMut(u32, &mut [u8])) -> Vec<u8> {
+    let mut out = encode_pwg_header(page);
+    let bpl = page.bytes_per_line();
+    let bpp = page.bytes_per_pixel();
+    let mut current = alloc::vec![0u8; bpl];
+    let mut next = alloc::vec![0u8; bpl];
+    if page.height == 0 || bpl == 0 {
+        return out;
+    }
+    row(0, &mut current);
+    let mut y = 0u32;
+    while y < page.height {
+        let mut repeat = 0u32;
+        while repeat < 255 && y + repeat + 1 < page.height {
+            row(y + repeat + 1, &mut next);
+            if next != current {
+                break;
+            }
+            repeat += 1;
+        }
+        out.push(repeat as u8);
+        encode_line(&mut out, &current, bpp);
+        y += repeat + 1;
+        if y < page.height {
+            if next == current {
+                // The repeat count ran out on a line equal to this one.
+                row(y, &mut next);
+            }
+            core::mem::swap(&mut current, &mut next);
+        }
+    }
+    out
+}
+
+/// Decode the page at the start of `data` (after the sync word). Returns
+/// the header fields, the raw pixels and the bytes consumed.
+pub fn decode_pwg_page(data: &[u8]) -> Option<(PwgPage, Vec<u8>, usize)> {
+    let header = data.get(..PWG_HEADER_LEN)?;
+    let width = get_u32(header, OFF_WIDTH)?;
+    let height = get_u32(header, OFF_HEIGHT)?;
+    let color = match get_u32(header, OFF_COLOR_SPACE)? {
+        PWG_COLOR_SPACE_SRGB => true,
+        PWG_COLOR_SPACE_SGRAY => false,
+        _ => return None,
+    };
+    if width > PWG_MAX_DIMENSION || height > PWG_MAX_DIMENSION || get_u32(header, OFF_BITS_PER_COLOR)? != 8 {
+        return None;
+    }
+    let page = PwgPage {
+        width,
+        height,
+        dpi: get_u32(header, OFF_HW_RESOLUTION)?,
+        color,
+        copies: get_u32(header, OFF_NUM_COPIES)?,
+        total_pages: get_u32(header, OFF_TOTAL_PAGE_COUNT)?,
+        page_size_pt: (get_u32(header, OFF_PAGE_SIZE)?, get_u32(header, OFF_PAGE_SIZE + 4)?),
+        media: "",
+    };
+    let bpp = page.bytes_per_pixel();
+    let bpl = page.bytes_per_line();
+    if get_u32(header, OFF_BYTES_PER_LINE)? as usize != bpl {
+        return None;
+    }
+    // Reserve by what the input can plausibly hold, not what the header claims.
+    let mut pixels = Vec::with_capacity((bpl * height as usize).min(data.len() * 8));
+    let mut pos = PWG_HEADER_LEN;
+    let mut line = Vec::with_capacity(bpl);
+    while pixels.len() < bpl * height as usize {
+        let repeat = *data.get(pos)? as usize;
+        pos += 1;
+        line.clear();
+        while line.len() < bpl {
+            let control = *data.get(pos)?;
+            pos += 1;
+            let (count, literal) = match control {
+                0..=127 => (control as usize + 1, false),
+                129..=255 => (257 - control as usize, true),
+                _ => return None,
+            };
+            if line.len() + count * bpp > bpl {
+                return None;
+            }
+            if literal {
+                line.extend_from_slice(data.get(pos..pos + count * bpp)?);
+                pos += count * bpp;
+            } else {
+                let value = data.get(pos..pos + bpp)?;
+                for _ in 0..count {
+                    line.extend_from_slice(value);
+                }
+                pos += bpp;
+            }
+        }
+        for _ in 0..=repeat {
+            if pixels.len() >= bpl * height as usize {
+                return None;
+            }
+            pixels.extend_from_slice(&line);
+        }
+    }
+    Some((page, pixels, pos))
+}
@@ -0,0 +1,134 @@
+//! SMTP submission (RFC 5321, RFC 6409): replies, `EHLO` extensions,
+//! `AUTH PLAIN` and the `DATA` transparency rule.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::mime::base64_encode;
+
+pub const SMTP_SUBMISSION_PORT: u16 = 587;
+pub const SMTPS_PORT: u16 = 465;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SmtpReply {
+    pub code: u16,
+    /// Text of each line without the code and separator.
+    pub lines: Vec<String>,
+    pub consumed: usize,
+}
+
+impl SmtpReply {
+    pub fn class(&self) -> u16 {
+        self.code / 100
+    }
+
+    /// Last line, for error messages.
+    pub fn message(&self) -> &str {
+        self.lines.last().map(String::as_str).unwrap_or("")
+    }
+}
+
+/// Parse the first complete reply in `buf`. Every line of a multi-line reply
+/// carries the code, with `-` after it on all but the last.
+pub fn parse_smtp_reply(buf: &[u8]) -> Option<SmtpReply> {
+    let mut pos = 0usize;
+    let mut lines = Vec::new();
+    let mut code = None;
+    loop {
+        let rest = buf.get(pos..)?;
+        let end = rest.iter().position(|&b| b == b'\n')?;
+        let line = &rest[..end];
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        pos += end + 1;
+
+        if line.len() < 3 || !line[..3].iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        let this_code = (line[0] - b'0') as u16 * 100 + (line[1] - b'0') as u16 * 10 + (line[2] - b'0') as u16;
+        if !(200..600).contains(&this_code) || code.is_some_and(|c| c != this_code) {
+            return None;
+        }
+        code = Some(this_code);
+        lines.push(String::from_utf8_lossy(line.get(4..).unwrap_or(&[])).into_owned());
+        match line.get(3) {
+            Some(b'-') => continue,
+            Some(b' ') | None => {
+                return Some(SmtpReply {
+                    code: this_code,
+                    lines,
+                    consumed: pos,
+                })
+            }
+            Some(_) => return None,
+        }
+    }
+}
+
+/// Whether an `EHLO` reply advertises `keyword` (e.g. `STARTTLS`). The first
+/// line is the server greeting, not an extension.
+pub fn ehlo_has(reply: &SmtpReply, keyword: &str) -> bool {
+    reply
+        .lines
+        .iter()
+        .skip(1)
+        .any(|l| l.split_whitespace().next().is_some_and(|k| k.eq_ignore_ascii_case(keyword)))
+}
+
+/// Mechanisms listed by the `AUTH` extension of an `EHLO` reply.
+pub fn ehlo_auth_mechanisms(reply: &SmtpReply) -> Vec<String> {
+    let mut out = Vec::new();
+    for line in reply.lines.iter().skip(1) {
+        let mut words = line.split_whitespace();
+        if words.next().is_some_and(|k| k.eq_ignore_ascii_case("AUTH")) {
+            out.extend(words.map(|w| w.to_ascii_uppercase()));
+        }
+    }
+    out
+}
+
+/// Initial response for `AUTH PLAIN` (RFC 4616): base64 of
+/// `\0user\0password`.
+pub fn auth_plain(user: &str, password: &str) -> String {
+    let mut raw = Vec::with_capacity(user.len() + password.len() + 2);
+    raw.push(0);
+    raw.extend_from_slice(user.as_bytes());
+    raw.push(0);
+    raw.extend_from_slice(password.as_bytes());
+    base64_encode(raw.as_slice())
+}
+
+/// Message text as sent after `DATA`: bare LF becomes CRLF, lines starting
+/// with `.` get a second dot, and the terminating `.` line is appended.
+pub fn dot_stuff(message: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(message.len() + 16);
+    let mut at_line_start = true;
+    let mut i = 0usize;
+    while i < message.len() {
+        let b = message[i];
+        if at_line_start && b == b'.' {
+            out.push(b'.');
+        }
+        match b {
+            b'\r' if message.get(i + 1) == Some(&b'\n') => {
+                out.extend_from_slice(b"\r\n");
+                i += 2;
+                at_line_start = true;
+                continue;
+            }
+            b'\n' => {
+                out.extend_from_slice(b"\r\n");
+                at_line_start = true;
+            }
+            _ => {
+                out.push(b);
+                at_line_start = false;
+            }
+        }
+        i += 1;
+    }
+    if !at_line_start {
+        out.extend_from_slice(b"\r\n");
+    }
+    out.extend_from_slice(b".\r\n");
+    out
+}
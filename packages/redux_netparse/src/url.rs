@@ -0,0 +1,152 @@
+//! `http://` / `https://` URL splitting and relative reference resolution.
+
+use alloc::string::String;
+
+use crate::starts_with_ignore_ascii_case;
+
+fn strip_scheme(url: &str) -> &str {
+    if starts_with_ignore_ascii_case(url, "http://") {
+        &url[7..]
+    } else if starts_with_ignore_ascii_case(url, "https://") {
+        &url[8..]
+    } else {
+        url
+    }
+}
+
+/// Host part of `url`, without scheme, port or path.
+pub fn extract_url_host(url: &str) -> Option<&str> {
+    let without_scheme = strip_scheme(url);
+
+    let authority = match without_scheme.find('/') {
+        Some(idx) => &without_scheme[..idx],
+        None => without_scheme,
+    };
+    let host = match authority.find(':') {
+        Some(idx) => &authority[..idx],
+        None => authority,
+    };
+
+    if host.is_empty() {
+        None
+    } else {
+        Some(host)
+    }
+}
+
+/// Split `url` into `(host, port, path)`. The port defaults to 80; callers
+/// switch to 443 for `https://` themselves.
+pub fn parse_url(url: &str) -> Option<(String, u16, &str)> {
+    let url = strip_scheme(url);
+
+    let (addr_part, path) = match url.find('/') {
+        Some(idx) => (&url[..idx], &url[idx..]),
+        None => (url, "/"),
+    };
+
+    let (host, port) = match addr_part.find(':') {
+        Some(idx) => (String::from(&addr_part[..idx]), addr_part[idx + 1..].parse().ok()?),
+        None => (String::from(addr_part), 80),
+    };
+
+    Some((host, port, path))
+}
+
+/// `scheme://authority` prefix of an absolute URL, if it has one.
+fn scheme_authority(url: &str) -> Option<&str> {
+    let scheme_end = url.find("://")?;
+    let rest = &url[scheme_end + 3..];
+    let authority_len = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    Some(&url[..scheme_end + 3 + authority_len])
+}
+
+fn has_scheme(reference: &str) -> bool {
+    match reference.find(':') {
+        Some(idx) if idx > 0 => reference[..idx]
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'-' || b == b'.'),
+        _ => false,
+    }
+}
+
+/// RFC 3986 section 5.2.4: drop `.` segments and fold `..` into the parent.
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments: alloc::vec::Vec<&str> = alloc::vec::Vec::new();
+    let mut iter = path.split('/').peekable();
+    // A leading "/" produces an empty first segment; keep it as the root.
+    let absolute = path.starts_with('/');
+    if absolute {
+        iter.next();
+    }
+    while let Some(segment) = iter.next() {
+        let last = iter.peek().is_none();
+        match segment {
+            "." => {
+                if last {
+                    segments.push("");
+                }
+            }
+            ".." => {
+                segments.pop();
+                if last {
+                    segments.push("");
+                }
+            }
+            _ => segments.push(segment),
+        }
+    }
+    let mut out = String::new();
+    if absolute {
+        out.push('/');
+    }
+    out.push_str(segments.join("/").as_str());
+    out
+}
+
+/// Resolve a link found in a document at `base` (an absolute URL with any
+/// scheme) into an absolute URL. Fragments are dropped.
+pub fn resolve_reference(base: &str, reference: &str) -> String {
+    let reference = reference.trim();
+    let reference = match reference.find('#') {
+        Some(idx) => &reference[..idx],
+        None => reference,
+    };
+    if has_scheme(reference) {
+        return String::from(reference);
+    }
+    let base = match base.find('#') {
+        Some(idx) => &base[..idx],
+        None => base,
+    };
+    let Some(origin) = scheme_authority(base) else {
+        return String::from(reference);
+    };
+    if reference.is_empty() {
+        return String::from(base);
+    }
+    if let Some(network_path) = reference.strip_prefix("//") {
+        let scheme_end = base.find("://").unwrap_or(0);
+        return alloc::format!("{}://{}", &base[..scheme_end], network_path);
+    }
+
+    let base_rest = &base[origin.len()..];
+    let (base_path, _) = base_rest.split_once('?').unwrap_or((base_rest, ""));
+    if reference.starts_with('?') {
+        return alloc::format!("{}{}{}", origin, base_path, reference);
+    }
+
+    let (ref_path, ref_query) = match reference.find('?') {
+        Some(idx) => (&reference[..idx], &reference[idx..]),
+        None => (reference, ""),
+    };
+    let merged = if ref_path.starts_with('/') {
+        String::from(ref_path)
+    } else {
+        let dir = match base_path.rfind('/') {
+            Some(idx) => &base_path[..idx + 1],
+            None => "/",
+        };
+        alloc::format!("{}{}", dir, ref_path)
+    };
+    alloc::format!("{}{}{}", origin, remove_dot_segments(merged.as_str()), ref_query)
+}
@@ -0,0 +1,506 @@
+//! Property tests over pseudo-random inputs. The generator is seeded, so a
+//! failure names the case that reproduces it; `NETPARSE_CASES` raises the
+//! number of cases per property (default 2000).
+
+use redux_netparse::cookie::*;
+use redux_netparse::dhcp::*;
+use redux_netparse::dns::*;
+use redux_netparse::ftp::*;
+use redux_netparse::gemini::*;
+use redux_netparse::gopher::*;
+use redux_netparse::hpack::*;
+use redux_netparse::http::*;
+use redux_netparse::imap::*;
+use redux_netparse::ipp::*;
+use redux_netparse::mime::*;
+use redux_netparse::mss::*;
+use redux_netparse::neighbor::*;
+use redux_netparse::pwg::*;
+use redux_netparse::smtp::*;
+use redux_netparse::url::*;
+
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+
+    fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+        let len = self.below(max_len + 1);
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+
+    /// Bytes biased towards the delimiters the parsers care about.
+    fn protocol_bytes(&mut self, max_len: usize) -> Vec<u8> {
+        const ALPHABET: &[u8] = b"\r\n:;= /0123456789abcdefABCDEF\t.-";
+        let len = self.below(max_len + 1);
+        (0..len)
+            .map(|_| if self.below(4) == 0 { self.next() as u8 } else { ALPHABET[self.below(ALPHABET.len())] })
+            .collect()
+    }
+
+    fn token(&mut self, max_len: usize) -> String {
+        const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789-_.";
+        let len = 1 + self.below(max_len);
+        (0..len).map(|_| ALPHABET[self.below(ALPHABET.len())] as char).collect()
+    }
+
+    fn text(&mut self, max_len: usize) -> String {
+        let len = self.below(max_len + 1);
+        (0..len).map(|_| (0x20 + self.below(0x5F)) as u8 as char).collect()
+    }
+}
+
+fn cases() -> u64 {
+    std::env::var("NETPARSE_CASES").ok().and_then(|v| v.parse().ok()).unwrap_or(2000)
+}
+
+fn for_each_case(seed: u64, mut f: impl FnMut(&mut Rng, u64)) {
+    for case in 0..cases() {
+        let mut rng = Rng::new(seed ^ case);
+        f(&mut rng, case);
+    }
+}
+
+#[test]
+fn arbitrary_bytes_never_panic() {
+    for_each_case(1, |rng, _| {
+        let raw = if rng.below(2) == 0 { rng.bytes(256) } else { rng.protocol_bytes(256) };
+        let parsed = parse_http_headers(&raw);
+        assert!(parsed.body_offset <= raw.len());
+        let _ = http_decode_chunked_body(&raw);
+        let _ = hpack_decode_huffman(&raw);
+        let mut table = HpackDynamicTable::new();
+        let mut headers = Vec::new();
+        let mut status = None;
+        let _ = hpack_decode_header_block(&raw, &mut table, &mut headers, &mut status);
+        assert!(table.size() <= HPACK_DYNAMIC_TABLE_ABSOLUTE_MAX);
+        let text = String::from_utf8_lossy(&raw);
+        let _ = parse_url(&text);
+        let _ = http_parse_set_cookie(&text, "example.com", "/a/b", rng.below(2) == 0, 0);
+        if let Some(head) = parse_gemini_header(&raw) {
+            assert!(head.body_offset <= raw.len());
+        }
+        let _ = parse_gemtext(&text);
+        let _ = parse_gemini_url(&text);
+        let _ = parse_gopher_url(&text);
+        for item in parse_gopher_menu(&text) {
+            let _ = item.url();
+        }
+        let _ = gopher_text_body(&text);
+        let _ = resolve_reference("gemini://host/a/b", &text);
+        if let Some(reply) = parse_ftp_reply(&raw) {
+            assert!(reply.consumed <= raw.len());
+        }
+        let _ = parse_pasv_reply(&text);
+        let _ = parse_epsv_reply(&text);
+        let _ = parse_pwd_reply(&text);
+        let _ = parse_list(&text);
+        if let Some(target) = parse_ftp_target(&text) {
+            assert!(ftp_arg_is_safe(&target.path));
+        }
+        let _ = parse_message(&raw);
+        let _ = base64_decode(&raw);
+        let _ = quoted_printable_decode(&raw);
+        let _ = decode_encoded_words(&text);
+        let _ = extract_address(&text);
+        if let Some(response) = parse_imap_response(&raw, "A1") {
+            assert!(response.consumed <= raw.len());
+            for line in response.untagged.iter() {
+                let _ = parse_fetch(line);
+                let _ = parse_exists(line);
+            }
+        }
+        if let Some(reply) = parse_smtp_reply(&raw) {
+            assert!(reply.consumed <= raw.len());
+        }
+        let _ = parse_dhcp_ack_options(&raw);
+        if let Some(lease) = DhcpLeaseRecord::parse(&text) {
+            assert_eq!(DhcpLeaseRecord::parse(&lease.to_text()), Some(lease));
+        }
+        let _ = observe_frame(&raw);
+        let mut frame = raw.clone();
+        if clamp_tcp_mss(&mut frame, 1460).is_some() {
+            assert_eq!(frame.len(), raw.len());
+            assert!(ipv4_tcp_syn_destination(&frame).is_some());
+        }
+        if let Some(mac) = parse_mac(&text) {
+            assert_eq!(parse_mac(&mac_text(&mac)), Some(mac));
+        }
+        let _ = parse_dns_message(&raw);
+        if let Some(msg) = parse_ipp_message(&raw) {
+            assert!(msg.data_offset <= raw.len());
+        }
+        if let Some((_, pixels, used)) = decode_pwg_page(&raw) {
+            assert!(used <= raw.len() && pixels.len() <= raw.len() * 128);
+        }
+        let _ = ipp_http_url(&text);
+    });
+}
+
+#[test]
+fn header_roundtrip() {
+    for_each_case(2, |rng, case| {
+        let status = 100 + rng.below(500) as u16;
+        let mut raw = format!("HTTP/1.1 {} Reason\r\n", status);
+        let mut expected = Vec::new();
+        for _ in 0..rng.below(8) {
+            let name = rng.token(12);
+            let value = rng.text(24).trim().to_string();
+            raw.push_str(&format!("{}: {}\r\n", name.to_ascii_uppercase(), value));
+            expected.push((name, value));
+        }
+        raw.push_str("\r\n");
+        let body = rng.bytes(32);
+        let mut bytes = raw.clone().into_bytes();
+        bytes.extend_from_slice(&body);
+
+        let parsed = parse_http_headers(&bytes);
+        assert_eq!(parsed.status_code, Some(status), "case {}", case);
+        assert_eq!(parsed.headers, expected, "case {}", case);
+        assert_eq!(&bytes[parsed.body_offset..], &body[..], "case {}", case);
+    });
+}
+
+#[test]
+fn chunked_roundtrip() {
+    for_each_case(3, |rng, case| {
+        let body = rng.bytes(512);
+        let mut encoded = Vec::new();
+        let mut rest = &body[..];
+        while !rest.is_empty() {
+            let take = 1 + rng.below(rest.len().min(64));
+            let size = if rng.below(2) == 0 { format!("{:x}", take) } else { format!("{:X};ext=1", take) };
+            encoded.extend_from_slice(size.as_bytes());
+            encoded.extend_from_slice(b"\r\n");
+            encoded.extend_from_slice(&rest[..take]);
+            encoded.extend_from_slice(b"\r\n");
+            rest = &rest[take..];
+        }
+        encoded.extend_from_slice(b"0\r\n\r\n");
+        assert_eq!(http_decode_chunked_body(&encoded).as_deref(), Some(&body[..]), "case {}", case);
+
+        // Any strict prefix that cuts a chunk short is rejected, never misread.
+        let cut = rng.below(encoded.len());
+        if let Some(decoded) = http_decode_chunked_body(&encoded[..cut]) {
+            assert!(body.starts_with(&decoded), "case {}", case);
+        }
+    });
+}
+
+#[test]
+fn hpack_integer_roundtrip() {
+    for_each_case(4, |rng, case| {
+        let prefix_bits = 1 + rng.below(8) as u8;
+        let value = match rng.below(3) {
+            0 => rng.below(300) as u32,
+            1 => rng.next() as u32 >> rng.below(32),
+            _ => u32::MAX - rng.below(4) as u32,
+        };
+        let mut out = vec![0xAA];
+        hpack_encode_prefixed_integer(&mut out, 0, prefix_bits, value);
+        let mut idx = 1;
+        assert_eq!(hpack_decode_prefixed_integer(&out, &mut idx, prefix_bits), Some(value), "case {}", case);
+        assert_eq!(idx, out.len(), "case {}", case);
+    });
+}
+
+#[test]
+fn hpack_huffman_roundtrip() {
+    for_each_case(5, |rng, case| {
+        let raw = rng.bytes(64);
+        let text = String::from_utf8_lossy(&raw).into_owned();
+        let mut encoded = Vec::new();
+        hpack_encode_string_huffman(&mut encoded, &text);
+        let mut idx = 0;
+        assert_eq!(hpack_decode_string(&encoded, &mut idx).as_deref(), Some(text.as_str()), "case {}", case);
+        assert_eq!(idx, encoded.len(), "case {}", case);
+    });
+}
+
+#[test]
+fn hpack_header_block_roundtrip() {
+    for_each_case(6, |rng, case| {
+        let mut encoder = HpackDynamicTable::new();
+        let mut decoder = HpackDynamicTable::new();
+        // Several blocks share the tables, like requests on one connection.
+        for _ in 0..3 {
+            let mut block = Vec::new();
+            let mut expected = Vec::new();
+            for _ in 0..1 + rng.below(6) {
+                let name = match rng.below(3) {
+                    0 => String::from("accept"),
+                    1 => String::from("user-agent"),
+                    _ => rng.token(10),
+                };
+                let value = rng.text(20);
+                hpack_encode_request_header(&mut block, &mut encoder, &name, &value, rng.below(2) == 0);
+                expected.push((name, value));
+            }
+            let mut headers = Vec::new();
+            let mut status = None;
+            assert!(hpack_decode_header_block(&block, &mut decoder, &mut headers, &mut status), "case {}", case);
+            assert_eq!(headers, expected, "case {}", case);
+            assert_eq!(decoder.len(), encoder.len(), "case {}", case);
+            assert_eq!(decoder.size(), encoder.size(), "case {}", case);
+        }
+    });
+}
+
+#[test]
+fn cookie_invariants() {
+    for_each_case(7, |rng, case| {
+        let host = format!("{}.{}.com", rng.token(6), rng.token(6));
+        let mut path = String::new();
+        for _ in 0..rng.below(4) {
+            path.push('/');
+            path.push_str(&rng.token(5));
+        }
+        let header = format!("{}={}; {}", rng.token(8), rng.token(8), String::from_utf8_lossy(&rng.protocol_bytes(40)));
+        if let Some(cookie) = http_parse_set_cookie(&header, &host, &path, true, 50) {
+            assert!(http_cookie_domain_matches(&host, &cookie.domain, cookie.host_only), "case {}", case);
+            assert!(cookie.path.starts_with('/'), "case {}", case);
+        }
+        let default = http_cookie_default_path(&path);
+        assert!(http_cookie_path_matches(&path, &default) || path.is_empty(), "case {}", case);
+    });
+}
+
+#[test]
+fn url_roundtrip() {
+    for_each_case(8, |rng, case| {
+        let host = rng.token(16);
+        let port = rng.below(65536) as u16;
+        let path = format!("/{}", rng.token(20));
+        let scheme = ["http://", "HTTPS://", ""][rng.below(3)];
+        let url = format!("{}{}:{}{}", scheme, host, port, path);
+        let (h, p, rest) = parse_url(&url).unwrap_or_else(|| panic!("case {}: {}", case, url));
+        assert_eq!((h.as_str(), p, rest), (host.as_str(), port, path.as_str()), "case {}", case);
+        assert_eq!(extract_url_host(&url), Some(host.as_str()), "case {}", case);
+    });
+}
+
+#[test]
+fn gopher_url_roundtrip() {
+    for_each_case(9, |rng, case| {
+        let item = GopherItem {
+            item_type: b"01479gI"[rng.below(7)],
+            display: rng.text(20),
+            selector: rng.text(30),
+            host: rng.token(12),
+            port: 1 + rng.below(65535) as u16,
+        };
+        let url = item.url().unwrap();
+        let back = parse_gopher_url(&url).unwrap_or_else(|| panic!("case {}: {}", case, url));
+        assert_eq!(back.item_type, item.item_type, "case {}", case);
+        assert_eq!(back.selector, item.selector, "case {}", case);
+        assert_eq!((back.host.as_str(), back.port), (item.host.as_str(), item.port), "case {}", case);
+    });
+}
+
+#[test]
+fn resolved_references_stay_on_origin() {
+    for_each_case(10, |rng, case| {
+        let base = format!("gemini://{}/{}/{}", rng.token(8), rng.token(6), rng.token(6));
+        let mut reference = String::new();
+        for _ in 0..1 + rng.below(5) {
+            reference.push_str(["..", ".", "x", "y/"][rng.below(4)]);
+            reference.push('/');
+        }
+        let resolved = resolve_reference(&base, &reference);
+        let origin_len = base[9..].find('/').unwrap() + 9;
+        assert!(resolved.starts_with(&base[..origin_len + 1]), "case {}: {}", case, resolved);
+        assert!(!resolved[origin_len..].split('/').any(|s| s == ".." || s == "."), "case {}: {}", case, resolved);
+    });
+}
+
+#[test]
+fn ftp_multiline_reply_roundtrip() {
+    for_each_case(11, |rng, case| {
+        let code = 100 + rng.below(500) as u16;
+        let mut lines = Vec::new();
+        for _ in 0..rng.below(4) {
+            lines.push(rng.token(16));
+        }
+        let last = rng.token(16);
+        let mut raw = String::new();
+        if lines.is_empty() {
+            raw.push_str(&format!("{} {}\r\n", code, last));
+        } else {
+            raw.push_str(&format!("{}-{}\r\n", code, lines[0]));
+            for line in &lines[1..] {
+                raw.push_str(&format!(" {}\r\n", line));
+            }
+            raw.push_str(&format!("{} {}\r\n", code, last));
+        }
+        let full = raw.len();
+        raw.push_str("999 trailing");
+        let reply = parse_ftp_reply(raw.as_bytes()).unwrap_or_else(|| panic!("case {}: {:?}", case, raw));
+        assert_eq!((reply.code, reply.consumed), (code, full), "case {}", case);
+        assert_eq!(reply.text.rsplit('\n').next(), Some(last.as_str()), "case {}", case);
+        assert!(parse_ftp_reply(&raw.as_bytes()[..full - 1]).is_none(), "case {}", case);
+    });
+}
+
+#[test]
+fn base64_roundtrip() {
+    for_each_case(12, |rng, case| {
+        let data = rng.bytes(96);
+        let encoded = base64_encode(&data);
+        assert_eq!(encoded.len() % 4, 0, "case {}", case);
+        // Line wrapping, as in mail bodies, does not change the result.
+        let wrapped: String = encoded
+            .as_bytes()
+            .chunks(1 + rng.below(76))
+            .map(|c| format!("{}\r\n", std::str::from_utf8(c).unwrap()))
+            .collect();
+        assert_eq!(base64_decode(wrapped.as_bytes()).as_deref(), Some(&data[..]), "case {}", case);
+        let text = rng.text(20);
+        let word = format!("=?UTF-8?B?{}?=", base64_encode(text.as_bytes()));
+        assert_eq!(decode_encoded_words(&word), text, "case {}", case);
+    });
+}
+
+#[test]
+fn smtp_dot_stuffing_roundtrip() {
+    for_each_case(13, |rng, case| {
+        let mut lines = Vec::new();
+        for _ in 0..rng.below(6) {
+            let prefix = [".", "..", "", "x"][rng.below(4)];
+            lines.push(format!("{}{}x", prefix, rng.text(12)));
+        }
+        let message = lines.join(["\n", "\r\n"][rng.below(2)]);
+        let stuffed = dot_stuff(message.as_bytes());
+        let text = String::from_utf8(stuffed).unwrap();
+        let body = text.strip_suffix(".\r\n").unwrap_or_else(|| panic!("case {}", case));
+        // The only lone "." line is the terminator.
+        assert!(!body.split("\r\n").any(|l| l == "."), "case {}", case);
+        let unstuffed: Vec<&str> = body
+            .strip_suffix("\r\n")
+            .unwrap_or(body)
+            .split("\r\n")
+            .map(|l| l.strip_prefix('.').unwrap_or(l))
+            .collect();
+        if !lines.is_empty() {
+            assert_eq!(unstuffed, lines, "case {}", case);
+        }
+    });
+}
+
+#[test]
+fn imap_literal_roundtrip() {
+    for_each_case(14, |rng, case| {
+        let literal = rng.bytes(64);
+        let seq = 1 + rng.below(500) as u32;
+        let uid = rng.next() as u32;
+        let raw = [
+            format!("* {} FETCH (UID {} BODY[] {{{}}}\r\n", seq, uid, literal.len()).into_bytes(),
+            literal.clone(),
+            b")\r\nT1 OK done\r\n".to_vec(),
+        ]
+        .concat();
+        let response = parse_imap_response(&raw, "T1").unwrap_or_else(|| panic!("case {}", case));
+        assert_eq!(response.consumed, raw.len(), "case {}", case);
+        let fetch = parse_fetch(&response.untagged[0]).unwrap();
+        assert_eq!((fetch.seq, fetch.uid), (seq, Some(uid)), "case {}", case);
+        assert_eq!(fetch.body.as_deref(), Some(&literal[..]), "case {}", case);
+        assert!(parse_imap_response(&raw[..raw.len() - 1], "T1").is_none(), "case {}", case);
+    });
+}
+
+#[test]
+fn pwg_page_roundtrip() {
+    for_each_case(15, |rng, case| {
+        let page = PwgPage {
+            width: 1 + rng.below(40) as u32,
+            height: 1 + rng.below(300) as u32,
+            dpi: 150,
+            color: rng.below(2) == 0,
+            copies: 1,
+            total_pages: 1,
+            page_size_pt: (595, 842),
+            media: "iso_a4_210x297mm",
+        };
+        // Few distinct values so both runs and repeated lines show up.
+        let palette = rng.bytes(4);
+        let palette = if palette.is_empty() { vec![0] } else { palette };
+        let mut expected = vec![0u8; page.bytes_per_line() * page.height as usize];
+        let mut y = 0;
+        while y < page.height as usize {
+            let max_repeat = if rng.below(4) == 0 { 300 } else { 3 };
+            let repeat = 1 + rng.below(max_repeat);
+            let line: Vec<u8> = (0..page.bytes_per_line()).map(|_| palette[rng.below(palette.len())]).collect();
+            for _ in 0..repeat.min(page.height as usize - y) {
+                expected[y * line.len()..(y + 1) * line.len()].copy_from_slice(&line);
+                y += 1;
+            }
+        }
+        let bpl = page.bytes_per_line();
+        let encoded = encode_pwg_page(&page, &mut |y, line| {
+            line.copy_from_slice(&expected[y as usize * bpl..(y as usize + 1) * bpl])
+        });
+        let (_, pixels, used) = decode_pwg_page(&encoded).unwrap_or_else(|| panic!("case {}", case));
+        assert_eq!(used, encoded.len(), "case {}", case);
+        assert_eq!(pixels, expected, "case {}", case);
+    });
+}
+
+#[test]
+fn ipp_request_roundtrip() {
+    for_each_case(16, |rng, case| {
+        let request_id = rng.next() as u32;
+        let mut request = IppRequest::new(IPP_OP_PRINT_JOB, request_id, "en");
+        let mut expected = Vec::new();
+        for _ in 0..rng.below(6) {
+            let name = rng.token(16);
+            let values: Vec<String> = (0..1 + rng.below(3)).map(|_| rng.text(24)).collect();
+            let refs: Vec<&str> = values.iter().map(|v| v.as_str()).collect();
+            request.strings(IPP_TAG_KEYWORD, &name, &refs);
+            expected.push((name, values));
+        }
+        let value = rng.next() as i32;
+        request.group(IPP_TAG_JOB).integer("copies", value);
+        let data = rng.bytes(64);
+        let bytes = request.finish(&data);
+
+        let msg = parse_ipp_message(&bytes).unwrap_or_else(|| panic!("case {}", case));
+        assert_eq!((msg.code, msg.request_id), (IPP_OP_PRINT_JOB, request_id), "case {}", case);
+        for (i, (name, values)) in expected.iter().enumerate() {
+            let attr = &msg.attributes[2 + i];
+            assert_eq!(&attr.name, name, "case {}", case);
+            assert_eq!(attr.values.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>(), *values, "case {}", case);
+        }
+        assert_eq!(msg.first_int(IPP_TAG_JOB, "copies"), Some(value), "case {}", case);
+        assert_eq!(&bytes[msg.data_offset..], &data[..], "case {}", case);
+    });
+}
+
+#[test]
+fn dns_query_roundtrip() {
+    for_each_case(17, |rng, case| {
+        let names: Vec<String> = (0..1 + rng.below(4))
+            .map(|_| (0..1 + rng.below(4)).map(|_| rng.token(20).replace('.', "x")).collect::<Vec<_>>().join("."))
+            .collect();
+        let questions: Vec<(&str, u16)> = names.iter().map(|n| (n.as_str(), DNS_TYPE_PTR)).collect();
+        let id = rng.next() as u16;
+        let query = build_dns_query(id, &questions, rng.below(2) == 0).unwrap_or_else(|| panic!("case {}", case));
+        let msg = parse_dns_message(&query).unwrap_or_else(|| panic!("case {}", case));
+        assert_eq!((msg.id, msg.is_response, msg.records.len()), (id, false, 0), "case {}", case);
+        // Any truncation drops the message instead of misreading it.
+        assert!(parse_dns_message(&query[..rng.below(query.len())]).is_none(), "case {}", case);
+    });
+}
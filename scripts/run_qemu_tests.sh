@@ -0,0 +1,80 @@
+#!/usr/bin/env bash
+# Boot the kernel headless in QEMU with the test harness enabled and run a
+# command spec against it (see kernel/src/testharness.rs, tools/qemu_harness.py).
+set -euo pipefail
+
+ESP_DIR="${1:-build/esp}"
+SPEC="${2:-tests/qemu/smoke.txt}"
+QEMU_BIN="${QEMU:-qemu-system-x86_64}"
+HARNESS_PORT="${HARNESS_PORT:-4555}"
+HARNESS_TIMEOUT="${HARNESS_TIMEOUT:-180}"
+BUILD_DIR="$(cd "${ESP_DIR}"/.. && pwd)"
+LOG_DIR="${BUILD_DIR}/qemu-tests"
+
+find_first() {
+  for p in "$@"; do
+    if [ -f "$p" ]; then
+      echo "$p"
+      return 0
+    fi
+  done
+  return 1
+}
+
+OVMF_CODE="$(find_first \
+  /usr/share/OVMF/OVMF_CODE.fd \
+  /usr/share/OVMF/OVMF_CODE_4M.fd \
+  /usr/share/edk2/ovmf/OVMF_CODE.fd \
+  /usr/share/edk2/x64/OVMF_CODE.fd \
+  /opt/homebrew/share/qemu/edk2-x86_64-code.fd \
+  /usr/local/share/qemu/edk2-x86_64-code.fd \
+  2>/dev/null || true)"
+OVMF_VARS_TEMPLATE="$(find_first \
+  /usr/share/OVMF/OVMF_VARS.fd \
+  /usr/share/OVMF/OVMF_VARS_4M.fd \
+  /usr/share/edk2/ovmf/OVMF_VARS.fd \
+  /usr/share/edk2/x64/OVMF_VARS.fd \
+  /opt/homebrew/share/qemu/edk2-x86_64-vars.fd \
+  /usr/local/share/qemu/edk2-x86_64-vars.fd \
+  2>/dev/null || true)"
+
+if [ -z "${OVMF_CODE}" ] || [ -z "${OVMF_VARS_TEMPLATE}" ]; then
+  echo "[error] OVMF firmware not found."
+  exit 1
+fi
+
+mkdir -p "${LOG_DIR}"
+# Fresh NVRAM per run so boot options written by a previous run do not leak in.
+cp "${OVMF_VARS_TEMPLATE}" "${LOG_DIR}/OVMF_VARS.fd"
+echo "\EFI\BOOT\BOOTX64.EFI testharness" > "${ESP_DIR}/startup.nsh"
+
+"${QEMU_BIN}" \
+  -machine q35 \
+  -m 1024 \
+  -display none \
+  -no-reboot \
+  -drive "if=pflash,format=raw,readonly=on,file=${OVMF_CODE}" \
+  -drive "if=pflash,format=raw,file=${LOG_DIR}/OVMF_VARS.fd" \
+  -drive "format=raw,file=fat:rw:${ESP_DIR}" \
+  -device virtio-net-pci,netdev=net0 \
+  -netdev "user,id=net0" \
+  -device virtio-tablet-pci \
+  -device virtio-keyboard-pci \
+  -device isa-debug-exit,iobase=0xf4,iosize=0x04 \
+  -debugcon "file:${LOG_DIR}/debugcon.log" \
+  -serial "file:${LOG_DIR}/console.log" \
+  -serial "tcp:127.0.0.1:${HARNESS_PORT},server=on,wait=off" &
+QEMU_PID=$!
+
+set +e
+python3 tools/qemu_harness.py "127.0.0.1:${HARNESS_PORT}" "${SPEC}" "${HARNESS_TIMEOUT}"
+RESULT=$?
+wait "${QEMU_PID}"
+QEMU_STATUS=$?
+set -e
+
+# Restore the interactive startup script used by scripts/run_uefi.sh.
+echo "\EFI\BOOT\BOOTX64.EFI" > "${ESP_DIR}/startup.nsh"
+
+echo "[info] debugcon log: ${LOG_DIR}/debugcon.log (qemu exit ${QEMU_STATUS})"
+exit "${RESULT}"
@@ -0,0 +1,98 @@
+#!/usr/bin/env bash
+# sign_secureboot.sh — Prepara el ESP para arrancar con Secure Boot via shim.
+#
+# Anade la seccion .sbat al kernel, lo firma con la clave MOK del proyecto y
+# deja en EFI/BOOT:
+#   BOOTX64.EFI  shim firmado por Microsoft (lo que arranca el firmware)
+#   SHIMX64.EFI  copia de shim para que el instalador la lleve al disco
+#   MMX64.EFI    MokManager
+#   GRUBX64.EFI  kernel firmado (shim carga este nombre)
+#   REDUX64.EFI  el mismo kernel firmado
+#   REDUX.CER    certificado DER que hay que inscribir con MokManager
+#
+# Si la clave o el certificado no existen se generan (RSA 2048, 10 anos).
+#
+# Uso: bash scripts/sign_secureboot.sh --esp-dir build/esp --image <kernel.efi> \
+#        --key <clave.key> --cert <cert.crt> --shim <shimx64.efi> \
+#        --mok-manager <mmx64.efi> [--sbat boot/sbat.csv]
+set -euo pipefail
+
+ESP_DIR=""
+IMAGE=""
+KEY=""
+CERT=""
+SHIM=""
+MOK_MANAGER=""
+SBAT="boot/sbat.csv"
+
+while [ $# -gt 0 ]; do
+  case "$1" in
+    --esp-dir) ESP_DIR="$2"; shift 2 ;;
+    --image) IMAGE="$2"; shift 2 ;;
+    --key) KEY="$2"; shift 2 ;;
+    --cert) CERT="$2"; shift 2 ;;
+    --shim) SHIM="$2"; shift 2 ;;
+    --mok-manager) MOK_MANAGER="$2"; shift 2 ;;
+    --sbat) SBAT="$2"; shift 2 ;;
+    *) echo "ERROR: opcion desconocida: $1"; exit 1 ;;
+  esac
+done
+
+for pair in "esp-dir:$ESP_DIR" "image:$IMAGE" "key:$KEY" "cert:$CERT" "shim:$SHIM" "mok-manager:$MOK_MANAGER"; do
+  if [ -z "${pair#*:}" ]; then
+    echo "ERROR: falta --${pair%%:*}"
+    exit 1
+  fi
+done
+for file in "$IMAGE" "$SHIM" "$MOK_MANAGER" "$SBAT"; do
+  if [ ! -f "$file" ]; then
+    echo "ERROR: no existe $file"
+    exit 1
+  fi
+done
+for tool in sbsign objcopy objdump openssl; do
+  if ! command -v "$tool" &>/dev/null; then
+    echo "ERROR: falta $tool (Linux: sudo apt install sbsigntool binutils openssl)"
+    exit 1
+  fi
+done
+
+if [ ! -f "$KEY" ] || [ ! -f "$CERT" ]; then
+  echo "Generando clave MOK en $KEY / $CERT"
+  mkdir -p "$(dirname "$KEY")" "$(dirname "$CERT")"
+  openssl req -new -x509 -newkey rsa:2048 -nodes -sha256 -days 3650 \
+    -subj "/CN=Zenox OS Secure Boot/" -keyout "$KEY" -out "$CERT"
+  chmod 600 "$KEY"
+fi
+
+WORK_DIR="$(mktemp -d)"
+trap 'rm -rf "$WORK_DIR"' EXIT
+
+# .sbat va detras de la ultima seccion, alineada a pagina.
+END=0
+while read -r _idx _name size vma _rest; do
+  end=$(( 16#$vma + 16#$size ))
+  if [ "$end" -gt "$END" ]; then
+    END=$end
+  fi
+done < <(objdump -h "$IMAGE" | grep -E '^ +[0-9]+ ')
+SBAT_VMA=$(printf '0x%x' $(( (END + 0xfff) & ~0xfff )))
+
+objcopy --add-section .sbat="$SBAT" \
+  --set-section-flags .sbat=contents,alloc,load,readonly,data \
+  --change-section-vma .sbat="$SBAT_VMA" \
+  "$IMAGE" "$WORK_DIR/redux64.efi"
+sbsign --key "$KEY" --cert "$CERT" --output "$WORK_DIR/redux64.signed.efi" "$WORK_DIR/redux64.efi"
+
+BOOT_DIR="$ESP_DIR/EFI/BOOT"
+mkdir -p "$BOOT_DIR"
+cp "$SHIM" "$BOOT_DIR/BOOTX64.EFI"
+cp "$SHIM" "$BOOT_DIR/SHIMX64.EFI"
+cp "$MOK_MANAGER" "$BOOT_DIR/MMX64.EFI"
+cp "$WORK_DIR/redux64.signed.efi" "$BOOT_DIR/GRUBX64.EFI"
+cp "$WORK_DIR/redux64.signed.efi" "$BOOT_DIR/REDUX64.EFI"
+openssl x509 -in "$CERT" -outform der -out "$BOOT_DIR/REDUX.CER"
+
+echo "ESP listo para Secure Boot en $BOOT_DIR"
+echo "Si la clave no esta inscrita, shim abre MokManager: 'Enroll key from disk' -> EFI/BOOT/REDUX.CER."
+echo "Desde el sistema, 'secureboot enroll' deja la inscripcion lista para el siguiente arranque."
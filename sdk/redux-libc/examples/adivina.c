@@ -0,0 +1,37 @@
+/* adivina: guess the number between 1 and 100, a console game port sample. */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+
+int main(void) {
+    struct timespec now;
+    clock_gettime(CLOCK_MONOTONIC, &now);
+    srand((unsigned int)(now.tv_nsec ^ now.tv_sec));
+    int secret = rand() % 100 + 1;
+
+    printf("Adivina el numero entre 1 y 100 (q para salir)\n");
+    char line[64];
+    for (int tries = 1;; tries++) {
+        printf("intento %d> ", tries);
+        if (!fgets(line, sizeof line, stdin) || line[0] == 'q') {
+            printf("\nEra %d.\n", secret);
+            return 0;
+        }
+        int guess;
+        if (sscanf(line, "%d", &guess) != 1) {
+            printf("escribe un numero\n");
+            tries--;
+            continue;
+        }
+        if (guess < secret) {
+            printf("mas alto\n");
+        } else if (guess > secret) {
+            printf("mas bajo\n");
+        } else {
+            printf("Correcto en %d intentos.\n", tries);
+            return 0;
+        }
+    }
+}
@@ -0,0 +1,63 @@
+/* wc: lines, words and bytes of files or stdin, as the classic utility. */
+
+#include <ctype.h>
+#include <stdio.h>
+#include <string.h>
+
+struct counts {
+    long lines;
+    long words;
+    long bytes;
+};
+
+static void count(FILE *f, struct counts *c) {
+    int in_word = 0;
+    int ch;
+    while ((ch = fgetc(f)) != EOF) {
+        c->bytes++;
+        if (ch == '\n') {
+            c->lines++;
+        }
+        if (isspace(ch)) {
+            in_word = 0;
+        } else if (!in_word) {
+            in_word = 1;
+            c->words++;
+        }
+    }
+}
+
+static void report(const struct counts *c, const char *name) {
+    printf("%7ld %7ld %7ld%s%s\n", c->lines, c->words, c->bytes, name ? " " : "", name ? name : "");
+}
+
+int main(int argc, char **argv) {
+    struct counts total = {0, 0, 0};
+    int status = 0;
+    if (argc < 2) {
+        count(stdin, &total);
+        report(&total, NULL);
+        return 0;
+    }
+    for (int i = 1; i < argc; i++) {
+        FILE *f = strcmp(argv[i], "-") == 0 ? stdin : fopen(argv[i], "r");
+        if (!f) {
+            perror(argv[i]);
+            status = 1;
+            continue;
+        }
+        struct counts c = {0, 0, 0};
+        count(f, &c);
+        report(&c, argv[i]);
+        total.lines += c.lines;
+        total.words += c.words;
+        total.bytes += c.bytes;
+        if (f != stdin) {
+            fclose(f);
+        }
+    }
+    if (argc > 2) {
+        report(&total, "total");
+    }
+    return status;
+}
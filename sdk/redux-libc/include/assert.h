@@ -0,0 +1,9 @@
+#pragma once
+
+void __assert_fail(const char *expr, const char *file, int line, const char *func);
+
+#ifdef NDEBUG
+#define assert(e) ((void)0)
+#else
+#define assert(e) ((e) ? (void)0 : __assert_fail(#e, __FILE__, __LINE__, __func__))
+#endif
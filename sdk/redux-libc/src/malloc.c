@@ -0,0 +1,205 @@
+/*
+ * malloc on brk with an address-ordered first-fit free list.
+ *
+ * Every block starts with a 16-byte header. Free blocks keep the next free
+ * block in their payload, and neighbours merge on free. Requests of
+ * MMAP_THRESHOLD or more get their own mapping and go back with munmap.
+ */
+
+#include <errno.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "syscall.h"
+
+#define ALIGN 16
+#define MMAP_THRESHOLD (128 * 1024)
+#define GROW_MIN (64 * 1024)
+#define MIN_SPLIT 32
+
+#define PROT_READ 1
+#define PROT_WRITE 2
+#define MAP_PRIVATE 0x02
+#define MAP_ANONYMOUS 0x20
+
+struct header {
+    size_t size; /* payload bytes, multiple of ALIGN */
+    size_t mapped;
+};
+
+struct free_block {
+    struct header h;
+    struct free_block *next;
+};
+
+static struct free_block *free_list;
+static uintptr_t brk_current;
+
+static size_t round_up(size_t n) {
+    return (n + ALIGN - 1) & ~(size_t)(ALIGN - 1);
+}
+
+int brk(void *addr) {
+    uintptr_t got = (uintptr_t)__syscall1(SYS_brk, addr);
+    brk_current = got;
+    if (got < (uintptr_t)addr) {
+        errno = ENOMEM;
+        return -1;
+    }
+    return 0;
+}
+
+void *sbrk(long increment) {
+    if (!brk_current) {
+        brk_current = (uintptr_t)__syscall1(SYS_brk, 0);
+    }
+    uintptr_t old = brk_current;
+    if (increment == 0) {
+        return (void *)old;
+    }
+    if (brk((void *)(old + increment)) < 0) {
+        return (void *)-1;
+    }
+    return (void *)old;
+}
+
+static void *map_pages(size_t bytes) {
+    long ret = __syscall6(SYS_mmap, 0, (long)bytes, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (ret < 0 && ret > -4096) {
+        return NULL;
+    }
+    return (void *)ret;
+}
+
+/* Puts b back in address order and merges it with the blocks it touches. */
+static void insert_free(struct free_block *b) {
+    struct free_block **link = &free_list;
+    struct free_block *prev = NULL;
+    while (*link && *link < b) {
+        prev = *link;
+        link = &(*link)->next;
+    }
+    b->next = *link;
+    *link = b;
+    if (b->next && (char *)b + sizeof(struct header) + b->h.size == (char *)b->next) {
+        b->h.size += sizeof(struct header) + b->next->h.size;
+        b->next = b->next->next;
+    }
+    if (prev && (char *)prev + sizeof(struct header) + prev->h.size == (char *)b) {
+        prev->h.size += sizeof(struct header) + b->h.size;
+        prev->next = b->next;
+    }
+}
+
+static int grow(size_t need) {
+    size_t bytes = round_up(need + sizeof(struct header));
+    if (bytes < GROW_MIN) {
+        bytes = GROW_MIN;
+    }
+    void *mem = sbrk((long)bytes);
+    if (mem == (void *)-1) {
+        /* No more brk (quota or a mapping in the way): take pages instead. */
+        mem = map_pages(bytes);
+        if (!mem) {
+            return -1;
+        }
+    }
+    /* brk may start unaligned; the header must sit on ALIGN. */
+    uintptr_t start = ((uintptr_t)mem + ALIGN - 1) & ~(uintptr_t)(ALIGN - 1);
+    struct free_block *b = (struct free_block *)start;
+    b->h.size = bytes - (start - (uintptr_t)mem) - sizeof(struct header);
+    b->h.mapped = 0;
+    insert_free(b);
+    return 0;
+}
+
+void *malloc(size_t size) {
+    if (size == 0) {
+        size = 1;
+    }
+    if (size > SIZE_MAX / 2) {
+        errno = ENOMEM;
+        return NULL;
+    }
+    size = round_up(size);
+    if (size >= MMAP_THRESHOLD) {
+        struct header *h = map_pages(size + sizeof(struct header));
+        if (!h) {
+            errno = ENOMEM;
+            return NULL;
+        }
+        h->size = size;
+        h->mapped = 1;
+        return h + 1;
+    }
+    for (int attempt = 0; attempt < 2; attempt++) {
+        struct free_block **link = &free_list;
+        for (struct free_block *b = free_list; b; link = &b->next, b = b->next) {
+            if (b->h.size < size) {
+                continue;
+            }
+            if (b->h.size - size >= sizeof(struct header) + MIN_SPLIT) {
+                struct free_block *rest = (struct free_block *)((char *)(&b->h + 1) + size);
+                rest->h.size = b->h.size - size - sizeof(struct header);
+                rest->h.mapped = 0;
+                rest->next = b->next;
+                *link = rest;
+                b->h.size = size;
+            } else {
+                *link = b->next;
+            }
+            return &b->h + 1;
+        }
+        if (grow(size) < 0) {
+            break;
+        }
+    }
+    errno = ENOMEM;
+    return NULL;
+}
+
+void free(void *ptr) {
+    if (!ptr) {
+        return;
+    }
+    struct header *h = (struct header *)ptr - 1;
+    if (h->mapped) {
+        __syscall2(SYS_munmap, h, h->size + sizeof(struct header));
+        return;
+    }
+    insert_free((struct free_block *)h);
+}
+
+void *calloc(size_t count, size_t size) {
+    if (size && count > SIZE_MAX / size) {
+        errno = ENOMEM;
+        return NULL;
+    }
+    void *p = malloc(count * size);
+    if (p) {
+        memset(p, 0, count * size);
+    }
+    return p;
+}
+
+void *realloc(void *ptr, size_t size) {
+    if (!ptr) {
+        return malloc(size);
+    }
+    if (size == 0) {
+        free(ptr);
+        return NULL;
+    }
+    struct header *h = (struct header *)ptr - 1;
+    if (round_up(size) <= h->size) {
+        return ptr;
+    }
+    void *out = malloc(size);
+    if (out) {
+        memcpy(out, ptr, h->size);
+        free(ptr);
+    }
+    return out;
+}
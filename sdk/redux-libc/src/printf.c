@@ -0,0 +1,387 @@
+/*
+ * printf family: one formatter writing into a sink that is either a caller
+ * buffer (snprintf) or a small stack buffer flushed to a FILE.
+ *
+ * Supported: flags `-+ #0`, width and precision (also `*`), lengths
+ * `hh h l ll z j t L`, conversions `d i u o x X c s p f F e E g G %`.
+ */
+
+#include <stdarg.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+
+struct sink {
+    char *buf;
+    size_t cap;
+    size_t len;
+    FILE *file;
+    size_t total;
+};
+
+static void sink_flush(struct sink *s) {
+    if (s->file && s->len > 0) {
+        fwrite(s->buf, 1, s->len, s->file);
+        s->len = 0;
+    }
+}
+
+static void put(struct sink *s, char c) {
+    s->total++;
+    if (s->file) {
+        if (s->len == s->cap) {
+            sink_flush(s);
+        }
+        s->buf[s->len++] = c;
+    } else if (s->len + 1 < s->cap) {
+        s->buf[s->len++] = c;
+    }
+}
+
+static void put_repeat(struct sink *s, char c, int n) {
+    while (n-- > 0) {
+        put(s, c);
+    }
+}
+
+#define F_LEFT 1
+#define F_PLUS 2
+#define F_SPACE 4
+#define F_ALT 8
+#define F_ZERO 16
+
+/* Writes sign/prefix, digits and padding for a converted number. */
+static void put_number(struct sink *s, const char *prefix, const char *digits, int ndigits, int precision, int width,
+                       int flags) {
+    int zeros = precision > ndigits ? precision - ndigits : 0;
+    int plen = (int)strlen(prefix);
+    int body = plen + zeros + ndigits;
+    if (!(flags & F_LEFT) && (flags & F_ZERO) && precision < 0) {
+        zeros += width > body ? width - body : 0;
+        body = width > body ? width : body;
+    }
+    if (!(flags & F_LEFT)) {
+        put_repeat(s, ' ', width - body);
+    }
+    for (int i = 0; i < plen; i++) {
+        put(s, prefix[i]);
+    }
+    put_repeat(s, '0', zeros);
+    for (int i = 0; i < ndigits; i++) {
+        put(s, digits[i]);
+    }
+    if (flags & F_LEFT) {
+        put_repeat(s, ' ', width - body);
+    }
+}
+
+static int utoa(uintmax_t v, unsigned base, int upper, char *out) {
+    const char *digits = upper ? "0123456789ABCDEF" : "0123456789abcdef";
+    char tmp[64];
+    int n = 0;
+    do {
+        tmp[n++] = digits[v % base];
+        v /= base;
+    } while (v);
+    for (int i = 0; i < n; i++) {
+        out[i] = tmp[n - 1 - i];
+    }
+    return n;
+}
+
+/* Digits of v >= 0 as %f with prec decimals, or %e when exp_form is set. */
+static int dtoa(double v, int prec, int exp_form, int upper, int alt, char *out, int cap) {
+    int n = 0;
+    int exp10 = 0;
+    if (exp_form && v != 0.0) {
+        while (v >= 10.0) {
+            v /= 10.0;
+            exp10++;
+        }
+        while (v < 1.0) {
+            v *= 10.0;
+            exp10--;
+        }
+    }
+    double scale = 1.0;
+    for (int i = 0; i < prec; i++) {
+        scale *= 10.0;
+    }
+    double rounded = v * scale + 0.5;
+    if (rounded >= 1.8e19) {
+        /* Too wide for integer digits: fall back to scientific. */
+        return exp_form ? 0 : dtoa(v, prec, 1, upper, alt, out, cap);
+    }
+    uint64_t all = (uint64_t)rounded;
+    uint64_t ip = all / (uint64_t)scale;
+    uint64_t fp = all % (uint64_t)scale;
+    if (exp_form && ip >= 10) {
+        ip /= 10;
+        exp10++;
+        fp = all / 10 % (uint64_t)scale;
+    }
+    n += utoa(ip, 10, 0, out);
+    if (prec > 0 || alt) {
+        out[n++] = '.';
+    }
+    char frac[32];
+    int flen = utoa(fp, 10, 0, frac);
+    for (int i = flen; i < prec && n < cap; i++) {
+        out[n++] = '0';
+    }
+    for (int i = 0; i < flen && prec > 0 && n < cap; i++) {
+        out[n++] = frac[i];
+    }
+    if (exp_form) {
+        out[n++] = upper ? 'E' : 'e';
+        out[n++] = exp10 < 0 ? '-' : '+';
+        unsigned e = (unsigned)(exp10 < 0 ? -exp10 : exp10);
+        if (e < 10) {
+            out[n++] = '0';
+        }
+        n += utoa(e, 10, 0, out + n);
+    }
+    return n;
+}
+
+static void put_double(struct sink *s, double v, char conv, int precision, int width, int flags) {
+    char buf[96];
+    int upper = conv == 'F' || conv == 'E' || conv == 'G';
+    const char *sign = v < 0 || (v == 0.0 && 1.0 / v < 0) ? "-" : (flags & F_PLUS) ? "+" : (flags & F_SPACE) ? " " : "";
+    if (v < 0) {
+        v = -v;
+    }
+    if (v != v || v > 1.7976931348623157e308) {
+        const char *word = v != v ? (upper ? "NAN" : "nan") : (upper ? "INF" : "inf");
+        put_number(s, sign, word, 3, -1, width, flags & ~F_ZERO);
+        return;
+    }
+    if (precision < 0) {
+        precision = 6;
+    }
+    int n;
+    if (conv == 'g' || conv == 'G') {
+        int p = precision ? precision : 1;
+        int exp10 = 0;
+        for (double t = v; t >= 10.0; t /= 10.0) {
+            exp10++;
+        }
+        for (double t = v; t != 0.0 && t < 1.0; t *= 10.0) {
+            exp10--;
+        }
+        int exp_form = exp10 < -4 || exp10 >= p;
+        n = dtoa(v, exp_form ? p - 1 : p - 1 - exp10, exp_form, upper, flags & F_ALT, buf, (int)sizeof buf);
+        if (!(flags & F_ALT) && memchr(buf, '.', (size_t)n)) {
+            /* %g drops trailing zeros of the fraction. */
+            char *e = memchr(buf, upper ? 'E' : 'e', (size_t)n);
+            int mant = e ? (int)(e - buf) : n;
+            int keep = mant;
+            while (buf[keep - 1] == '0') {
+                keep--;
+            }
+            if (buf[keep - 1] == '.') {
+                keep--;
+            }
+            memmove(buf + keep, buf + mant, (size_t)(n - mant));
+            n -= mant - keep;
+        }
+    } else {
+        n = dtoa(v, precision, conv == 'e' || conv == 'E', upper, flags & F_ALT, buf, (int)sizeof buf);
+    }
+    put_number(s, sign, buf, n, -1, width, flags);
+}
+
+static void format(struct sink *s, const char *fmt, va_list ap) {
+    for (; *fmt; fmt++) {
+        if (*fmt != '%') {
+            put(s, *fmt);
+            continue;
+        }
+        fmt++;
+        int flags = 0;
+        for (;; fmt++) {
+            if (*fmt == '-') {
+                flags |= F_LEFT;
+            } else if (*fmt == '+') {
+                flags |= F_PLUS;
+            } else if (*fmt == ' ') {
+                flags |= F_SPACE;
+            } else if (*fmt == '#') {
+                flags |= F_ALT;
+            } else if (*fmt == '0') {
+                flags |= F_ZERO;
+            } else {
+                break;
+            }
+        }
+        int width = 0;
+        if (*fmt == '*') {
+            width = va_arg(ap, int);
+            if (width < 0) {
+                flags |= F_LEFT;
+                width = -width;
+            }
+            fmt++;
+        } else {
+            while (*fmt >= '0' && *fmt <= '9') {
+                width = width * 10 + (*fmt++ - '0');
+            }
+        }
+        int precision = -1;
+        if (*fmt == '.') {
+            fmt++;
+            precision = 0;
+            if (*fmt == '*') {
+                precision = va_arg(ap, int);
+                fmt++;
+            } else {
+                while (*fmt >= '0' && *fmt <= '9') {
+                    precision = precision * 10 + (*fmt++ - '0');
+                }
+            }
+        }
+        int size = 0; /* -2 hh, -1 h, 0 int, 1 long/ll/z/j/t */
+        if (*fmt == 'h') {
+            size = fmt[1] == 'h' ? -2 : -1;
+            fmt += fmt[1] == 'h' ? 2 : 1;
+        } else if (*fmt == 'l') {
+            size = 1;
+            fmt += fmt[1] == 'l' ? 2 : 1;
+        } else if (*fmt == 'z' || *fmt == 'j' || *fmt == 't') {
+            size = 1;
+            fmt++;
+        } else if (*fmt == 'L') {
+            fmt++;
+        }
+
+        char digits[64];
+        switch (*fmt) {
+        case 'd':
+        case 'i': {
+            intmax_t v = size == 1 ? va_arg(ap, long) : va_arg(ap, int);
+            if (size == -1) {
+                v = (short)v;
+            } else if (size == -2) {
+                v = (signed char)v;
+            }
+            uintmax_t mag = v < 0 ? 0 - (uintmax_t)v : (uintmax_t)v;
+            const char *sign = v < 0 ? "-" : (flags & F_PLUS) ? "+" : (flags & F_SPACE) ? " " : "";
+            int n = precision == 0 && v == 0 ? 0 : utoa(mag, 10, 0, digits);
+            put_number(s, sign, digits, n, precision, width, flags);
+            break;
+        }
+        case 'u':
+        case 'o':
+        case 'x':
+        case 'X': {
+            uintmax_t v = size == 1 ? va_arg(ap, unsigned long) : va_arg(ap, unsigned int);
+            if (size == -1) {
+                v = (unsigned short)v;
+            } else if (size == -2) {
+                v = (unsigned char)v;
+            }
+            unsigned base = *fmt == 'u' ? 10 : *fmt == 'o' ? 8 : 16;
+            int n = precision == 0 && v == 0 ? 0 : utoa(v, base, *fmt == 'X', digits);
+            const char *prefix = "";
+            if ((flags & F_ALT) && v != 0) {
+                prefix = base == 16 ? (*fmt == 'X' ? "0X" : "0x") : base == 8 ? "0" : "";
+            }
+            put_number(s, prefix, digits, n, precision, width, flags);
+            break;
+        }
+        case 'p': {
+            uintptr_t v = (uintptr_t)va_arg(ap, void *);
+            int n = utoa(v, 16, 0, digits);
+            put_number(s, "0x", digits, n, -1, width, flags & ~F_ZERO);
+            break;
+        }
+        case 'c':
+            digits[0] = (char)va_arg(ap, int);
+            put_number(s, "", digits, 1, -1, width, flags & ~F_ZERO);
+            break;
+        case 's': {
+            const char *str = va_arg(ap, const char *);
+            if (!str) {
+                str = "(null)";
+            }
+            int n = (int)(precision >= 0 ? strnlen(str, (size_t)precision) : strlen(str));
+            put_number(s, "", str, n, -1, width, flags & ~F_ZERO);
+            break;
+        }
+        case 'f':
+        case 'F':
+        case 'e':
+        case 'E':
+        case 'g':
+        case 'G':
+            put_double(s, va_arg(ap, double), *fmt, precision, width, flags);
+            break;
+        case '%':
+            put(s, '%');
+            break;
+        case '\0':
+            return;
+        default:
+            put(s, '%');
+            put(s, *fmt);
+            break;
+        }
+    }
+}
+
+int vsnprintf(char *buf, size_t size, const char *fmt, va_list ap) {
+    struct sink s = {buf, size, 0, NULL, 0};
+    format(&s, fmt, ap);
+    if (size > 0) {
+        buf[s.len] = '\0';
+    }
+    return (int)s.total;
+}
+
+int vsprintf(char *buf, const char *fmt, va_list ap) {
+    return vsnprintf(buf, SIZE_MAX, fmt, ap);
+}
+
+int vfprintf(FILE *f, const char *fmt, va_list ap) {
+    char buf[256];
+    struct sink s = {buf, sizeof buf, 0, f, 0};
+    format(&s, fmt, ap);
+    sink_flush(&s);
+    return ferror(f) ? -1 : (int)s.total;
+}
+
+int vprintf(const char *fmt, va_list ap) {
+    return vfprintf(stdout, fmt, ap);
+}
+
+int snprintf(char *buf, size_t size, const char *fmt, ...) {
+    va_list ap;
+    va_start(ap, fmt);
+    int n = vsnprintf(buf, size, fmt, ap);
+    va_end(ap);
+    return n;
+}
+
+int sprintf(char *buf, const char *fmt, ...) {
+    va_list ap;
+    va_start(ap, fmt);
+    int n = vsprintf(buf, fmt, ap);
+    va_end(ap);
+    return n;
+}
+
+int fprintf(FILE *f, const char *fmt, ...) {
+    va_list ap;
+    va_start(ap, fmt);
+    int n = vfprintf(f, fmt, ap);
+    va_end(ap);
+    return n;
+}
+
+int printf(const char *fmt, ...) {
+    va_list ap;
+    va_start(ap, fmt);
+    int n = vfprintf(stdout, fmt, ap);
+    va_end(ap);
+    return n;
+}
@@ -0,0 +1,176 @@
+/*
+ * sscanf: `%d i u o x X c s f e g n %`, `*` suppression, field widths and
+ * the `hh h l ll z` lengths. No scanset (`%[`).
+ */
+
+#include <ctype.h>
+#include <stdarg.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+static void store_int(va_list *ap, int size, unsigned long long v) {
+    switch (size) {
+    case -2:
+        *va_arg(*ap, char *) = (char)v;
+        break;
+    case -1:
+        *va_arg(*ap, short *) = (short)v;
+        break;
+    case 0:
+        *va_arg(*ap, int *) = (int)v;
+        break;
+    default:
+        *va_arg(*ap, long *) = (long)v;
+        break;
+    }
+}
+
+int vsscanf(const char *s, const char *fmt, va_list ap_in) {
+    va_list ap;
+    va_copy(ap, ap_in);
+    const char *start = s;
+    int assigned = 0;
+    for (; *fmt; fmt++) {
+        if (isspace((unsigned char)*fmt)) {
+            while (isspace((unsigned char)*s)) {
+                s++;
+            }
+            continue;
+        }
+        if (*fmt != '%' || fmt[1] == '%') {
+            if (*fmt == '%') {
+                fmt++;
+            }
+            if (*s != *fmt) {
+                break;
+            }
+            s++;
+            continue;
+        }
+        fmt++;
+        int suppress = *fmt == '*';
+        if (suppress) {
+            fmt++;
+        }
+        int width = 0;
+        while (isdigit((unsigned char)*fmt)) {
+            width = width * 10 + (*fmt++ - '0');
+        }
+        int size = 0;
+        if (*fmt == 'h') {
+            size = fmt[1] == 'h' ? -2 : -1;
+            fmt += fmt[1] == 'h' ? 2 : 1;
+        } else if (*fmt == 'l' || *fmt == 'z' || *fmt == 'j' || *fmt == 'L') {
+            size = 1;
+            fmt += fmt[0] == 'l' && fmt[1] == 'l' ? 2 : 1;
+        }
+        char conv = *fmt;
+        if (conv == 'n') {
+            if (!suppress) {
+                store_int(&ap, size, (unsigned long long)(s - start));
+            }
+            continue;
+        }
+        if (conv != 'c') {
+            while (isspace((unsigned char)*s)) {
+                s++;
+            }
+        }
+        if (!*s) {
+            if (assigned == 0) {
+                assigned = EOF;
+            }
+            break;
+        }
+        /* Numbers parse from a copy cut at the field width. */
+        char field[128];
+        size_t limit = width > 0 && (size_t)width < sizeof field ? (size_t)width : sizeof field - 1;
+        size_t flen = strnlen(s, limit);
+        memcpy(field, s, flen);
+        field[flen] = '\0';
+        char *end = field;
+        switch (conv) {
+        case 'd':
+        case 'i':
+        case 'u':
+        case 'o':
+        case 'x':
+        case 'X': {
+            int base = conv == 'd' || conv == 'u' ? 10 : conv == 'i' ? 0 : conv == 'o' ? 8 : 16;
+            unsigned long long v = conv == 'd' || conv == 'i' ? (unsigned long long)strtoll(field, &end, base)
+                                                              : strtoull(field, &end, base);
+            if (end == field) {
+                goto done;
+            }
+            if (!suppress) {
+                store_int(&ap, size, v);
+            }
+            break;
+        }
+        case 'f':
+        case 'e':
+        case 'g':
+        case 'E':
+        case 'G': {
+            double v = strtod(field, &end);
+            if (end == field) {
+                goto done;
+            }
+            if (!suppress) {
+                if (size == 1) {
+                    *va_arg(ap, double *) = v;
+                } else {
+                    *va_arg(ap, float *) = (float)v;
+                }
+            }
+            break;
+        }
+        case 'c': {
+            size_t n = width > 0 ? (size_t)width : 1;
+            if (strnlen(s, n) < n) {
+                goto done;
+            }
+            if (!suppress) {
+                memcpy(va_arg(ap, char *), s, n);
+            }
+            end = field + n;
+            break;
+        }
+        case 's': {
+            size_t n = 0;
+            size_t max = width > 0 ? (size_t)width : (size_t)-1;
+            while (s[n] && !isspace((unsigned char)s[n]) && n < max) {
+                n++;
+            }
+            if (!suppress) {
+                char *out = va_arg(ap, char *);
+                memcpy(out, s, n);
+                out[n] = '\0';
+            }
+            s += n;
+            if (!suppress) {
+                assigned++;
+            }
+            continue;
+        }
+        default:
+            goto done;
+        }
+        s += end - field;
+        if (!suppress) {
+            assigned++;
+        }
+    }
+done:
+    va_end(ap);
+    return assigned;
+}
+
+int sscanf(const char *s, const char *fmt, ...) {
+    va_list ap;
+    va_start(ap, fmt);
+    int n = vsscanf(s, fmt, ap);
+    va_end(ap);
+    return n;
+}
@@ -0,0 +1,458 @@
+/*
+ * Buffered FILE streams over file descriptors.
+ *
+ * A stream buffers in one direction at a time: reads fill `buf` from the
+ * descriptor, writes collect in it until it is full (or a newline on a
+ * line-buffered stream). Switching direction flushes pending writes or seeks
+ * back over unread input. Reading stdin flushes stdout first so prompts show.
+ */
+
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "syscall.h"
+
+#define F_READ 1
+#define F_WRITE 2
+#define F_EOF 4
+#define F_ERR 8
+#define F_APPEND 16
+
+struct __redux_file {
+    int fd;
+    int flags;
+    int buffering;
+    int ungot;
+    unsigned char *buf;
+    size_t size;
+    size_t rpos;
+    size_t rend;
+    size_t wlen;
+    int owns_buf;
+    struct __redux_file *next;
+};
+
+static unsigned char stdin_buf[BUFSIZ];
+static unsigned char stdout_buf[BUFSIZ];
+
+static FILE stdin_file = {STDIN_FILENO, F_READ, _IOLBF, EOF, stdin_buf, BUFSIZ, 0, 0, 0, 0, NULL};
+static FILE stdout_file = {STDOUT_FILENO, F_WRITE, _IOLBF, EOF, stdout_buf, BUFSIZ, 0, 0, 0, 0, &stdin_file};
+static FILE stderr_file = {STDERR_FILENO, F_WRITE, _IONBF, EOF, NULL, 0, 0, 0, 0, 0, &stdout_file};
+
+FILE *stdin = &stdin_file;
+FILE *stdout = &stdout_file;
+FILE *stderr = &stderr_file;
+
+static FILE *open_files = &stderr_file;
+
+static int write_out(FILE *f, const unsigned char *data, size_t len) {
+    while (len > 0) {
+        ssize_t n = write(f->fd, data, len);
+        if (n < 0) {
+            if (errno == EINTR) {
+                continue;
+            }
+            f->flags |= F_ERR;
+            return EOF;
+        }
+        data += n;
+        len -= (size_t)n;
+    }
+    return 0;
+}
+
+int fflush(FILE *f) {
+    if (!f) {
+        int result = 0;
+        for (FILE *it = open_files; it; it = it->next) {
+            if (it->wlen > 0 && fflush(it) == EOF) {
+                result = EOF;
+            }
+        }
+        return result;
+    }
+    if (f->wlen > 0) {
+        size_t len = f->wlen;
+        f->wlen = 0;
+        return write_out(f, f->buf, len);
+    }
+    if (f->rend > f->rpos && lseek(f->fd, -(off_t)(f->rend - f->rpos), SEEK_CUR) >= 0) {
+        /* Read-ahead went back to the descriptor; pipes and ttys keep it. */
+        f->rpos = f->rend = 0;
+    }
+    return 0;
+}
+
+void __stdio_exit(void) {
+    fflush(NULL);
+}
+
+static FILE *new_file(int fd, int flags) {
+    FILE *f = calloc(1, sizeof(FILE));
+    if (!f) {
+        return NULL;
+    }
+    f->buf = malloc(BUFSIZ);
+    if (!f->buf) {
+        free(f);
+        return NULL;
+    }
+    f->fd = fd;
+    f->flags = flags;
+    f->buffering = _IOFBF;
+    f->ungot = EOF;
+    f->size = BUFSIZ;
+    f->owns_buf = 1;
+    f->next = open_files;
+    open_files = f;
+    return f;
+}
+
+/* fopen mode string to open(2) flags and stream flags; -1 if invalid. */
+static int parse_mode(const char *mode, int *stream_flags) {
+    int plus = strchr(mode, '+') != NULL;
+    switch (mode[0]) {
+    case 'r':
+        *stream_flags = plus ? F_READ | F_WRITE : F_READ;
+        return plus ? O_RDWR : O_RDONLY;
+    case 'w':
+        *stream_flags = plus ? F_READ | F_WRITE : F_WRITE;
+        return (plus ? O_RDWR : O_WRONLY) | O_CREAT | O_TRUNC;
+    case 'a':
+        *stream_flags = (plus ? F_READ | F_WRITE : F_WRITE) | F_APPEND;
+        return (plus ? O_RDWR : O_WRONLY) | O_CREAT | O_APPEND;
+    default:
+        return -1;
+    }
+}
+
+FILE *fopen(const char *path, const char *mode) {
+    int stream_flags;
+    int flags = parse_mode(mode, &stream_flags);
+    if (flags < 0) {
+        errno = EINVAL;
+        return NULL;
+    }
+    int fd = open(path, flags | O_CLOEXEC, 0644);
+    if (fd < 0) {
+        return NULL;
+    }
+    if (stream_flags & F_APPEND) {
+        lseek(fd, 0, SEEK_END);
+    }
+    FILE *f = new_file(fd, stream_flags);
+    if (!f) {
+        close(fd);
+    }
+    return f;
+}
+
+FILE *fdopen(int fd, const char *mode) {
+    int stream_flags;
+    if (parse_mode(mode, &stream_flags) < 0) {
+        errno = EINVAL;
+        return NULL;
+    }
+    return new_file(fd, stream_flags);
+}
+
+int fclose(FILE *f) {
+    int result = fflush(f);
+    if (close(f->fd) < 0) {
+        result = EOF;
+    }
+    for (FILE **link = &open_files; *link; link = &(*link)->next) {
+        if (*link == f) {
+            *link = f->next;
+            break;
+        }
+    }
+    if (f == stdin || f == stdout || f == stderr) {
+        return result;
+    }
+    if (f->owns_buf) {
+        free(f->buf);
+    }
+    free(f);
+    return result;
+}
+
+int fileno(FILE *f) {
+    return f->fd;
+}
+
+int setvbuf(FILE *f, char *buf, int mode, size_t size) {
+    fflush(f);
+    if (mode == _IONBF) {
+        size = 0;
+    } else if (size == 0) {
+        size = BUFSIZ;
+    }
+    unsigned char *next = (unsigned char *)buf;
+    if (!next && size > 0) {
+        next = malloc(size);
+        if (!next) {
+            return EOF;
+        }
+    }
+    if (f->owns_buf) {
+        free(f->buf);
+    }
+    f->owns_buf = !buf && size > 0;
+    f->buf = next;
+    f->size = size;
+    f->buffering = mode;
+    return 0;
+}
+
+/* Moves a stream that was reading into writing mode. */
+static int start_write(FILE *f) {
+    if (!(f->flags & F_WRITE)) {
+        f->flags |= F_ERR;
+        errno = EBADF;
+        return EOF;
+    }
+    if (f->rend > f->rpos || f->ungot != EOF) {
+        f->ungot = EOF;
+        fflush(f);
+    }
+    f->rpos = f->rend = 0;
+    return 0;
+}
+
+size_t fwrite(const void *data, size_t size, size_t count, FILE *f) {
+    size_t total = size * count;
+    if (total == 0 || start_write(f) == EOF) {
+        return 0;
+    }
+    const unsigned char *p = data;
+    if (f->size == 0) {
+        return write_out(f, p, total) == EOF ? 0 : count;
+    }
+    size_t left = total;
+    while (left > 0) {
+        if (f->wlen == f->size && fflush(f) == EOF) {
+            return (total - left) / size;
+        }
+        if (f->wlen == 0 && left >= f->size) {
+            /* Big writes skip the buffer. */
+            if (write_out(f, p, left) == EOF) {
+                return 0;
+            }
+            return count;
+        }
+        size_t n = f->size - f->wlen;
+        if (n > left) {
+            n = left;
+        }
+        memcpy(f->buf + f->wlen, p, n);
+        f->wlen += n;
+        p += n;
+        left -= n;
+    }
+    if (f->buffering == _IOLBF && memchr(data, '\n', total) && fflush(f) == EOF) {
+        return 0;
+    }
+    return count;
+}
+
+int fputc(int c, FILE *f) {
+    unsigned char ch = (unsigned char)c;
+    return fwrite(&ch, 1, 1, f) == 1 ? ch : EOF;
+}
+
+int putc(int c, FILE *f) {
+    return fputc(c, f);
+}
+
+int putchar(int c) {
+    return fputc(c, stdout);
+}
+
+int fputs(const char *s, FILE *f) {
+    size_t len = strlen(s);
+    return fwrite(s, 1, len, f) == len ? 0 : EOF;
+}
+
+int puts(const char *s) {
+    if (fputs(s, stdout) == EOF) {
+        return EOF;
+    }
+    return fputc('\n', stdout) == EOF ? EOF : 0;
+}
+
+/* Refills the read buffer; 0 at end of file or on error. */
+static size_t fill(FILE *f) {
+    if (f == stdin) {
+        fflush(stdout);
+    }
+    if (f->wlen > 0 && fflush(f) == EOF) {
+        return 0;
+    }
+    unsigned char one;
+    unsigned char *dst = f->size ? f->buf : &one;
+    size_t cap = f->size ? f->size : 1;
+    ssize_t n;
+    do {
+        n = read(f->fd, dst, cap);
+    } while (n < 0 && errno == EINTR);
+    if (n <= 0) {
+        f->flags |= n == 0 ? F_EOF : F_ERR;
+        return 0;
+    }
+    if (!f->size) {
+        f->ungot = one;
+        return 1;
+    }
+    f->rpos = 0;
+    f->rend = (size_t)n;
+    return (size_t)n;
+}
+
+int fgetc(FILE *f) {
+    if (!(f->flags & F_READ)) {
+        f->flags |= F_ERR;
+        return EOF;
+    }
+    if (f->ungot != EOF) {
+        int c = f->ungot;
+        f->ungot = EOF;
+        return c;
+    }
+    if (f->rpos == f->rend && fill(f) == 0) {
+        return EOF;
+    }
+    if (f->ungot != EOF) {
+        int c = f->ungot;
+        f->ungot = EOF;
+        return c;
+    }
+    return f->buf[f->rpos++];
+}
+
+int getc(FILE *f) {
+    return fgetc(f);
+}
+
+int getchar(void) {
+    return fgetc(stdin);
+}
+
+int ungetc(int c, FILE *f) {
+    if (c == EOF || f->ungot != EOF) {
+        return EOF;
+    }
+    f->ungot = (unsigned char)c;
+    f->flags &= ~F_EOF;
+    return f->ungot;
+}
+
+size_t fread(void *data, size_t size, size_t count, FILE *f) {
+    size_t total = size * count;
+    unsigned char *p = data;
+    size_t got = 0;
+    while (got < total) {
+        if (f->ungot == EOF && f->rpos < f->rend) {
+            size_t n = f->rend - f->rpos;
+            if (n > total - got) {
+                n = total - got;
+            }
+            memcpy(p + got, f->buf + f->rpos, n);
+            f->rpos += n;
+            got += n;
+            continue;
+        }
+        int c = fgetc(f);
+        if (c == EOF) {
+            break;
+        }
+        p[got++] = (unsigned char)c;
+    }
+    return size ? got / size : 0;
+}
+
+char *fgets(char *buf, int size, FILE *f) {
+    if (size <= 0) {
+        return NULL;
+    }
+    int i = 0;
+    while (i < size - 1) {
+        int c = fgetc(f);
+        if (c == EOF) {
+            break;
+        }
+        buf[i++] = (char)c;
+        if (c == '\n') {
+            break;
+        }
+    }
+    if (i == 0) {
+        return NULL;
+    }
+    buf[i] = '\0';
+    return buf;
+}
+
+int fseek(FILE *f, long offset, int whence) {
+    if (whence == SEEK_CUR) {
+        offset -= (long)(f->rend - f->rpos) + (f->ungot != EOF);
+    }
+    if (f->wlen > 0 && fflush(f) == EOF) {
+        return -1;
+    }
+    f->rpos = f->rend = 0;
+    f->ungot = EOF;
+    if (lseek(f->fd, offset, whence) < 0) {
+        return -1;
+    }
+    f->flags &= ~F_EOF;
+    return 0;
+}
+
+long ftell(FILE *f) {
+    off_t pos = lseek(f->fd, 0, SEEK_CUR);
+    if (pos < 0) {
+        return -1;
+    }
+    return (long)(pos - (off_t)(f->rend - f->rpos) - (f->ungot != EOF) + (off_t)f->wlen);
+}
+
+void rewind(FILE *f) {
+    fseek(f, 0, SEEK_SET);
+    f->flags &= ~F_ERR;
+}
+
+int feof(FILE *f) {
+    return (f->flags & F_EOF) != 0;
+}
+
+int ferror(FILE *f) {
+    return (f->flags & F_ERR) != 0;
+}
+
+void clearerr(FILE *f) {
+    f->flags &= ~(F_EOF | F_ERR);
+}
+
+void perror(const char *prefix) {
+    if (prefix && *prefix) {
+        fprintf(stderr, "%s: %s\n", prefix, strerror(errno));
+    } else {
+        fprintf(stderr, "%s\n", strerror(errno));
+    }
+}
+
+int remove(const char *path) {
+    if (unlink(path) == 0) {
+        return 0;
+    }
+    return errno == EISDIR ? rmdir(path) : -1;
+}
+
+int rename(const char *from, const char *to) {
+    return (int)__syscall_ret(__syscall4(SYS_renameat, AT_FDCWD, from, AT_FDCWD, to));
+}
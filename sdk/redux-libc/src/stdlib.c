@@ -0,0 +1,250 @@
+#include <assert.h>
+#include <ctype.h>
+#include <errno.h>
+#include <limits.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+#define ATEXIT_MAX 32
+
+int main(int argc, char **argv, char **envp);
+void __stdio_exit(void);
+
+static char **environ_ptr;
+static void (*atexit_fns[ATEXIT_MAX])(void);
+static int atexit_count;
+
+/* Called by crt0 with the Linux entry stack already split up. */
+void __libc_start(int argc, char **argv, char **envp) {
+    environ_ptr = envp;
+    exit(main(argc, argv, envp));
+}
+
+int atexit(void (*fn)(void)) {
+    if (atexit_count == ATEXIT_MAX) {
+        return -1;
+    }
+    atexit_fns[atexit_count++] = fn;
+    return 0;
+}
+
+void exit(int status) {
+    while (atexit_count > 0) {
+        atexit_fns[--atexit_count]();
+    }
+    __stdio_exit();
+    _exit(status);
+}
+
+void abort(void) {
+    static const char msg[] = "abort\n";
+    write(STDERR_FILENO, msg, sizeof msg - 1);
+    _exit(134);
+}
+
+void __assert_fail(const char *expr, const char *file, int line, const char *func) {
+    fprintf(stderr, "%s:%d: %s: assertion '%s' failed\n", file, line, func, expr);
+    fflush(stderr);
+    abort();
+}
+
+char *getenv(const char *name) {
+    size_t len = strlen(name);
+    for (char **e = environ_ptr; e && *e; e++) {
+        if (strncmp(*e, name, len) == 0 && (*e)[len] == '=') {
+            return *e + len + 1;
+        }
+    }
+    return NULL;
+}
+
+/* Digits of s in base (0 picks 8, 10 or 16 from the prefix); saturates on overflow. */
+static unsigned long long parse_unsigned(const char *s, char **end, int base, int *negative, int *overflow) {
+    const char *p = s;
+    while (isspace((unsigned char)*p)) {
+        p++;
+    }
+    *negative = 0;
+    if (*p == '+' || *p == '-') {
+        *negative = *p == '-';
+        p++;
+    }
+    if ((base == 0 || base == 16) && p[0] == '0' && (p[1] == 'x' || p[1] == 'X') && isxdigit((unsigned char)p[2])) {
+        p += 2;
+        base = 16;
+    } else if (base == 0) {
+        base = p[0] == '0' ? 8 : 10;
+    }
+    unsigned long long value = 0;
+    const char *digits = p;
+    *overflow = 0;
+    for (;; p++) {
+        int d;
+        if (isdigit((unsigned char)*p)) {
+            d = *p - '0';
+        } else if (isalpha((unsigned char)*p)) {
+            d = tolower((unsigned char)*p) - 'a' + 10;
+        } else {
+            break;
+        }
+        if (d >= base) {
+            break;
+        }
+        if (value > (ULLONG_MAX - (unsigned)d) / (unsigned)base) {
+            *overflow = 1;
+        }
+        value = value * (unsigned)base + (unsigned)d;
+    }
+    if (end) {
+        *end = (char *)(p == digits ? s : p);
+    }
+    return *overflow ? ULLONG_MAX : value;
+}
+
+long long strtoll(const char *s, char **end, int base) {
+    int negative, overflow;
+    unsigned long long v = parse_unsigned(s, end, base, &negative, &overflow);
+    if (overflow || v > (unsigned long long)LLONG_MAX + negative) {
+        errno = ERANGE;
+        return negative ? LLONG_MIN : LLONG_MAX;
+    }
+    return negative ? (long long)(0 - v) : (long long)v;
+}
+
+unsigned long long strtoull(const char *s, char **end, int base) {
+    int negative, overflow;
+    unsigned long long v = parse_unsigned(s, end, base, &negative, &overflow);
+    if (overflow) {
+        errno = ERANGE;
+        return ULLONG_MAX;
+    }
+    return negative ? 0 - v : v;
+}
+
+long strtol(const char *s, char **end, int base) {
+    return (long)strtoll(s, end, base);
+}
+
+unsigned long strtoul(const char *s, char **end, int base) {
+    return (unsigned long)strtoull(s, end, base);
+}
+
+int atoi(const char *s) {
+    return (int)strtol(s, NULL, 10);
+}
+
+long atol(const char *s) {
+    return strtol(s, NULL, 10);
+}
+
+int abs(int v) {
+    return v < 0 ? -v : v;
+}
+
+long labs(long v) {
+    return v < 0 ? -v : v;
+}
+
+static unsigned long long rand_state = 1;
+
+void srand(unsigned int seed) {
+    rand_state = seed;
+}
+
+int rand(void) {
+    rand_state = rand_state * 6364136223846793005ULL + 1442695040888963407ULL;
+    return (int)(rand_state >> 33);
+}
+
+static void swap_bytes(char *a, char *b, size_t size) {
+    while (size--) {
+        char t = *a;
+        *a++ = *b;
+        *b++ = t;
+    }
+}
+
+/* Shell sort: no recursion and no scratch memory. */
+void qsort(void *base, size_t count, size_t size, int (*cmp)(const void *, const void *)) {
+    char *a = base;
+    size_t gap = 1;
+    while (gap < count / 3) {
+        gap = gap * 3 + 1;
+    }
+    for (; gap > 0; gap /= 3) {
+        for (size_t i = gap; i < count; i++) {
+            for (size_t j = i; j >= gap && cmp(a + (j - gap) * size, a + j * size) > 0; j -= gap) {
+                swap_bytes(a + (j - gap) * size, a + j * size, size);
+            }
+        }
+    }
+}
+
+void *bsearch(const void *key, const void *base, size_t count, size_t size, int (*cmp)(const void *, const void *)) {
+    const char *a = base;
+    while (count > 0) {
+        const char *mid = a + (count / 2) * size;
+        int c = cmp(key, mid);
+        if (c == 0) {
+            return (void *)mid;
+        }
+        if (c > 0) {
+            a = mid + size;
+            count -= count / 2 + 1;
+        } else {
+            count /= 2;
+        }
+    }
+    return NULL;
+}
+
+/* Decimal and exponent forms; no hex floats, inf or nan. */
+double strtod(const char *s, char **end) {
+    const char *p = s;
+    while (isspace((unsigned char)*p)) {
+        p++;
+    }
+    int negative = *p == '-';
+    if (*p == '+' || *p == '-') {
+        p++;
+    }
+    double value = 0.0;
+    int digits = 0;
+    for (; isdigit((unsigned char)*p); p++, digits++) {
+        value = value * 10.0 + (*p - '0');
+    }
+    if (*p == '.') {
+        double scale = 0.1;
+        for (p++; isdigit((unsigned char)*p); p++, digits++) {
+            value += (*p - '0') * scale;
+            scale *= 0.1;
+        }
+    }
+    if (digits == 0) {
+        if (end) {
+            *end = (char *)s;
+        }
+        return 0.0;
+    }
+    if ((*p == 'e' || *p == 'E') && (isdigit((unsigned char)p[1]) || ((p[1] == '+' || p[1] == '-') && isdigit((unsigned char)p[2])))) {
+        char *after;
+        long exp10 = strtol(p + 1, &after, 10);
+        p = after;
+        for (; exp10 > 0; exp10--) {
+            value *= 10.0;
+        }
+        for (; exp10 < 0; exp10++) {
+            value /= 10.0;
+        }
+    }
+    if (end) {
+        *end = (char *)p;
+    }
+    return negative ? -value : value;
+}
+
+double atof(const char *s) {
+    return strtod(s, NULL);
+}
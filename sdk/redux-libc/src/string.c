@@ -0,0 +1,283 @@
+#include <ctype.h>
+#include <errno.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+
+void *memcpy(void *dst, const void *src, size_t n) {
+    unsigned char *d = dst;
+    const unsigned char *s = src;
+    while (n--) {
+        *d++ = *s++;
+    }
+    return dst;
+}
+
+void *memmove(void *dst, const void *src, size_t n) {
+    unsigned char *d = dst;
+    const unsigned char *s = src;
+    if (d == s || n == 0) {
+        return dst;
+    }
+    if (d < s || d >= s + n) {
+        return memcpy(dst, src, n);
+    }
+    while (n--) {
+        d[n] = s[n];
+    }
+    return dst;
+}
+
+void *memset(void *dst, int c, size_t n) {
+    unsigned char *d = dst;
+    while (n--) {
+        *d++ = (unsigned char)c;
+    }
+    return dst;
+}
+
+int memcmp(const void *a, const void *b, size_t n) {
+    const unsigned char *x = a;
+    const unsigned char *y = b;
+    for (; n; n--, x++, y++) {
+        if (*x != *y) {
+            return *x - *y;
+        }
+    }
+    return 0;
+}
+
+void *memchr(const void *s, int c, size_t n) {
+    const unsigned char *p = s;
+    for (; n; n--, p++) {
+        if (*p == (unsigned char)c) {
+            return (void *)p;
+        }
+    }
+    return NULL;
+}
+
+size_t strlen(const char *s) {
+    const char *p = s;
+    while (*p) {
+        p++;
+    }
+    return (size_t)(p - s);
+}
+
+size_t strnlen(const char *s, size_t max) {
+    size_t n = 0;
+    while (n < max && s[n]) {
+        n++;
+    }
+    return n;
+}
+
+char *strcpy(char *dst, const char *src) {
+    char *d = dst;
+    while ((*d++ = *src++)) {
+    }
+    return dst;
+}
+
+/* Pads with NUL up to n and does not terminate a too-long src, as C says. */
+char *strncpy(char *dst, const char *src, size_t n) {
+    size_t i = 0;
+    for (; i < n && src[i]; i++) {
+        dst[i] = src[i];
+    }
+    for (; i < n; i++) {
+        dst[i] = '\0';
+    }
+    return dst;
+}
+
+char *strcat(char *dst, const char *src) {
+    strcpy(dst + strlen(dst), src);
+    return dst;
+}
+
+char *strncat(char *dst, const char *src, size_t n) {
+    char *d = dst + strlen(dst);
+    while (n-- && *src) {
+        *d++ = *src++;
+    }
+    *d = '\0';
+    return dst;
+}
+
+int strcmp(const char *a, const char *b) {
+    while (*a && *a == *b) {
+        a++;
+        b++;
+    }
+    return (unsigned char)*a - (unsigned char)*b;
+}
+
+int strncmp(const char *a, const char *b, size_t n) {
+    for (; n; n--, a++, b++) {
+        if (*a != *b || !*a) {
+            return (unsigned char)*a - (unsigned char)*b;
+        }
+    }
+    return 0;
+}
+
+int strcasecmp(const char *a, const char *b) {
+    while (*a && tolower((unsigned char)*a) == tolower((unsigned char)*b)) {
+        a++;
+        b++;
+    }
+    return tolower((unsigned char)*a) - tolower((unsigned char)*b);
+}
+
+int strncasecmp(const char *a, const char *b, size_t n) {
+    for (; n; n--, a++, b++) {
+        int x = tolower((unsigned char)*a);
+        int y = tolower((unsigned char)*b);
+        if (x != y || !x) {
+            return x - y;
+        }
+    }
+    return 0;
+}
+
+char *strchr(const char *s, int c) {
+    for (;; s++) {
+        if (*s == (char)c) {
+            return (char *)s;
+        }
+        if (!*s) {
+            return NULL;
+        }
+    }
+}
+
+char *strrchr(const char *s, int c) {
+    const char *last = NULL;
+    for (;; s++) {
+        if (*s == (char)c) {
+            last = s;
+        }
+        if (!*s) {
+            return (char *)last;
+        }
+    }
+}
+
+char *strstr(const char *haystack, const char *needle) {
+    size_t n = strlen(needle);
+    if (n == 0) {
+        return (char *)haystack;
+    }
+    for (; *haystack; haystack++) {
+        if (*haystack == *needle && strncmp(haystack, needle, n) == 0) {
+            return (char *)haystack;
+        }
+    }
+    return NULL;
+}
+
+size_t strspn(const char *s, const char *accept) {
+    size_t n = 0;
+    while (s[n] && strchr(accept, s[n])) {
+        n++;
+    }
+    return n;
+}
+
+size_t strcspn(const char *s, const char *reject) {
+    size_t n = 0;
+    while (s[n] && !strchr(reject, s[n])) {
+        n++;
+    }
+    return n;
+}
+
+char *strpbrk(const char *s, const char *accept) {
+    s += strcspn(s, accept);
+    return *s ? (char *)s : NULL;
+}
+
+char *strtok_r(char *s, const char *delim, char **save) {
+    if (!s) {
+        s = *save;
+    }
+    s += strspn(s, delim);
+    if (!*s) {
+        *save = s;
+        return NULL;
+    }
+    char *end = s + strcspn(s, delim);
+    if (*end) {
+        *end++ = '\0';
+    }
+    *save = end;
+    return s;
+}
+
+char *strtok(char *s, const char *delim) {
+    static char *save;
+    return strtok_r(s, delim, &save);
+}
+
+char *strndup(const char *s, size_t n) {
+    size_t len = strnlen(s, n);
+    char *out = malloc(len + 1);
+    if (out) {
+        memcpy(out, s, len);
+        out[len] = '\0';
+    }
+    return out;
+}
+
+char *strdup(const char *s) {
+    return strndup(s, SIZE_MAX);
+}
+
+char *strerror(int errnum) {
+    switch (errnum) {
+    case 0:
+        return "Success";
+    case EPERM:
+        return "Operation not permitted";
+    case ENOENT:
+        return "No such file or directory";
+    case EINTR:
+        return "Interrupted system call";
+    case EIO:
+        return "I/O error";
+    case EBADF:
+        return "Bad file descriptor";
+    case EAGAIN:
+        return "Resource temporarily unavailable";
+    case ENOMEM:
+        return "Out of memory";
+    case EACCES:
+        return "Permission denied";
+    case EFAULT:
+        return "Bad address";
+    case EEXIST:
+        return "File exists";
+    case ENOTDIR:
+        return "Not a directory";
+    case EISDIR:
+        return "Is a directory";
+    case EINVAL:
+        return "Invalid argument";
+    case EMFILE:
+        return "Too many open files";
+    case ENOSPC:
+        return "No space left on device";
+    case ESPIPE:
+        return "Illegal seek";
+    case ERANGE:
+        return "Result out of range";
+    case ENOSYS:
+        return "Function not implemented";
+    case ENOTEMPTY:
+        return "Directory not empty";
+    default:
+        return "Unknown error";
+    }
+}
@@ -0,0 +1,50 @@
+/* Raw x86_64 syscalls for the ReduxOS Linux ABI shim (internal). */
+#pragma once
+
+#include <stddef.h>
+
+#define SYS_read 0
+#define SYS_write 1
+#define SYS_close 3
+#define SYS_fstat 5
+#define SYS_ioctl 16
+#define SYS_lseek 8
+#define SYS_mmap 9
+#define SYS_munmap 11
+#define SYS_brk 12
+#define SYS_nanosleep 35
+#define SYS_getpid 39
+#define SYS_exit 60
+#define SYS_getcwd 79
+#define SYS_chdir 80
+#define SYS_clock_gettime 228
+#define SYS_exit_group 231
+#define SYS_openat 257
+#define SYS_mkdirat 258
+#define SYS_newfstatat 262
+#define SYS_unlinkat 263
+#define SYS_renameat 264
+
+#define AT_FDCWD (-100)
+#define AT_REMOVEDIR 0x200
+
+static inline long __syscall6(long n, long a0, long a1, long a2, long a3, long a4, long a5) {
+    long ret;
+    register long r10 __asm__("r10") = a3;
+    register long r8 __asm__("r8") = a4;
+    register long r9 __asm__("r9") = a5;
+    __asm__ volatile("syscall"
+                     : "=a"(ret)
+                     : "a"(n), "D"(a0), "S"(a1), "d"(a2), "r"(r10), "r"(r8), "r"(r9)
+                     : "rcx", "r11", "memory");
+    return ret;
+}
+
+#define __syscall0(n) __syscall6((n), 0, 0, 0, 0, 0, 0)
+#define __syscall1(n, a) __syscall6((n), (long)(a), 0, 0, 0, 0, 0)
+#define __syscall2(n, a, b) __syscall6((n), (long)(a), (long)(b), 0, 0, 0, 0)
+#define __syscall3(n, a, b, c) __syscall6((n), (long)(a), (long)(b), (long)(c), 0, 0, 0)
+#define __syscall4(n, a, b, c, d) __syscall6((n), (long)(a), (long)(b), (long)(c), (long)(d), 0, 0)
+
+/* Linux calls return -errno in -4095..-1; sets errno and returns -1 then. */
+long __syscall_ret(long ret);
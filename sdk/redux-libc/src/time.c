@@ -0,0 +1,66 @@
+#include <time.h>
+
+#include "syscall.h"
+
+int clock_gettime(clockid_t clock, struct timespec *ts) {
+    return (int)__syscall_ret(__syscall2(SYS_clock_gettime, clock, ts));
+}
+
+time_t time(time_t *out) {
+    struct timespec ts = {0, 0};
+    clock_gettime(CLOCK_REALTIME, &ts);
+    if (out) {
+        *out = ts.tv_sec;
+    }
+    return ts.tv_sec;
+}
+
+/* Microseconds since the first call; the shim has no per-process CPU clock. */
+clock_t clock(void) {
+    static struct timespec start;
+    struct timespec now;
+    if (clock_gettime(CLOCK_MONOTONIC, &now) < 0) {
+        return (clock_t)-1;
+    }
+    if (!start.tv_sec && !start.tv_nsec) {
+        start = now;
+    }
+    return (clock_t)((now.tv_sec - start.tv_sec) * CLOCKS_PER_SEC + (now.tv_nsec - start.tv_nsec) / 1000);
+}
+
+struct tm *gmtime_r(const time_t *t, struct tm *out) {
+    long days = *t / 86400;
+    long secs = *t % 86400;
+    if (secs < 0) {
+        secs += 86400;
+        days--;
+    }
+    out->tm_hour = (int)(secs / 3600);
+    out->tm_min = (int)(secs / 60 % 60);
+    out->tm_sec = (int)(secs % 60);
+    out->tm_wday = (int)((days % 7 + 11) % 7); /* 1970-01-01 was a Thursday */
+    out->tm_isdst = 0;
+
+    /* Days to civil date (H. Hinnant's algorithm). */
+    long z = days + 719468;
+    long era = (z >= 0 ? z : z - 146096) / 146097;
+    long doe = z - era * 146097;
+    long yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    long doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    long mp = (5 * doy + 2) / 153;
+    long month = mp < 10 ? mp + 3 : mp - 9;
+    long year = yoe + era * 400 + (month <= 2);
+    out->tm_mday = (int)(doy - (153 * mp + 2) / 5 + 1);
+    out->tm_mon = (int)(month - 1);
+    out->tm_year = (int)(year - 1900);
+
+    int leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    static const int before[12] = {0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334};
+    out->tm_yday = before[out->tm_mon] + out->tm_mday - 1 + (leap && out->tm_mon > 1);
+    return out;
+}
+
+struct tm *gmtime(const time_t *t) {
+    static struct tm tm;
+    return gmtime_r(t, &tm);
+}
@@ -0,0 +1,110 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdarg.h>
+#include <sys/stat.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "syscall.h"
+
+#define TCGETS 0x5401
+
+int errno;
+
+long __syscall_ret(long ret) {
+    if (ret < 0 && ret > -4096) {
+        errno = (int)-ret;
+        return -1;
+    }
+    return ret;
+}
+
+ssize_t read(int fd, void *buf, size_t count) {
+    return __syscall_ret(__syscall3(SYS_read, fd, buf, count));
+}
+
+ssize_t write(int fd, const void *buf, size_t count) {
+    return __syscall_ret(__syscall3(SYS_write, fd, buf, count));
+}
+
+int open(const char *path, int flags, ...) {
+    mode_t mode = 0;
+    if (flags & O_CREAT) {
+        va_list ap;
+        va_start(ap, flags);
+        mode = va_arg(ap, mode_t);
+        va_end(ap);
+    }
+    return (int)__syscall_ret(__syscall4(SYS_openat, AT_FDCWD, path, flags, mode));
+}
+
+int creat(const char *path, mode_t mode) {
+    return open(path, O_WRONLY | O_CREAT | O_TRUNC, mode);
+}
+
+int close(int fd) {
+    return (int)__syscall_ret(__syscall1(SYS_close, fd));
+}
+
+off_t lseek(int fd, off_t offset, int whence) {
+    return __syscall_ret(__syscall3(SYS_lseek, fd, offset, whence));
+}
+
+int stat(const char *path, struct stat *st) {
+    return (int)__syscall_ret(__syscall4(SYS_newfstatat, AT_FDCWD, path, st, 0));
+}
+
+int fstat(int fd, struct stat *st) {
+    return (int)__syscall_ret(__syscall2(SYS_fstat, fd, st));
+}
+
+int mkdir(const char *path, mode_t mode) {
+    return (int)__syscall_ret(__syscall3(SYS_mkdirat, AT_FDCWD, path, mode));
+}
+
+int unlink(const char *path) {
+    return (int)__syscall_ret(__syscall3(SYS_unlinkat, AT_FDCWD, path, 0));
+}
+
+int rmdir(const char *path) {
+    return (int)__syscall_ret(__syscall3(SYS_unlinkat, AT_FDCWD, path, AT_REMOVEDIR));
+}
+
+int chdir(const char *path) {
+    return (int)__syscall_ret(__syscall1(SYS_chdir, path));
+}
+
+char *getcwd(char *buf, size_t size) {
+    return __syscall_ret(__syscall2(SYS_getcwd, buf, size)) < 0 ? NULL : buf;
+}
+
+pid_t getpid(void) {
+    return (pid_t)__syscall0(SYS_getpid);
+}
+
+int isatty(int fd) {
+    /* struct termios is 60 bytes; only the answer matters. */
+    unsigned char termios[64];
+    return __syscall_ret(__syscall3(SYS_ioctl, fd, TCGETS, termios)) == 0;
+}
+
+int nanosleep(const struct timespec *req, struct timespec *rem) {
+    return (int)__syscall_ret(__syscall2(SYS_nanosleep, req, rem));
+}
+
+unsigned int sleep(unsigned int seconds) {
+    struct timespec ts = {seconds, 0};
+    return nanosleep(&ts, &ts) < 0 ? (unsigned int)ts.tv_sec : 0;
+}
+
+int usleep(unsigned long usec) {
+    struct timespec ts = {(time_t)(usec / 1000000), (long)(usec % 1000000) * 1000};
+    return nanosleep(&ts, NULL);
+}
+
+void _exit(int status) {
+    __syscall1(SYS_exit_group, status);
+    for (;;) {
+        __syscall1(SYS_exit, status);
+    }
+}
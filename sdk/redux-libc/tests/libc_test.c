@@ -0,0 +1,203 @@
+/*
+ * redux-libc self test. Runs on ReduxOS (`linux runloop start /LIBCTEST.BIN`)
+ * and on an x86_64 Linux host, which has the same syscall ABI:
+ *
+ *   make test-libc
+ */
+
+#include <assert.h>
+#include <ctype.h>
+#include <errno.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+static int failures;
+
+#define CHECK(cond)                                                                                                    \
+    do {                                                                                                               \
+        if (!(cond)) {                                                                                                 \
+            fprintf(stderr, "FAIL %s:%d: %s\n", __FILE__, __LINE__, #cond);                                            \
+            failures++;                                                                                                \
+        }                                                                                                              \
+    } while (0)
+
+#define CHECK_FMT(expected, ...)                                                                                       \
+    do {                                                                                                               \
+        char out[128];                                                                                                 \
+        snprintf(out, sizeof out, __VA_ARGS__);                                                                        \
+        if (strcmp(out, expected) != 0) {                                                                              \
+            fprintf(stderr, "FAIL %s:%d: got \"%s\", want \"%s\"\n", __FILE__, __LINE__, out, expected);               \
+            failures++;                                                                                                \
+        }                                                                                                              \
+    } while (0)
+
+static int cmp_int(const void *a, const void *b) {
+    return *(const int *)a - *(const int *)b;
+}
+
+static void test_string(void) {
+    char buf[32];
+    CHECK(strlen("redux") == 5);
+    CHECK(strcmp("abc", "abd") < 0 && strcmp("b", "a") > 0 && strcmp("x", "x") == 0);
+    CHECK(strncmp("abcdef", "abcxyz", 3) == 0);
+    CHECK(strcasecmp("ReduxOS", "reduxos") == 0);
+    strcpy(buf, "foo");
+    strcat(buf, "bar");
+    CHECK(strcmp(buf, "foobar") == 0);
+    CHECK(strchr(buf, 'b') == buf + 3 && strrchr("a/b/c", '/') != NULL && strchr(buf, 'z') == NULL);
+    CHECK(strstr("hello world", "wor") != NULL && strstr("hello", "xyz") == NULL);
+    CHECK(strspn("123abc", "0123456789") == 3 && strcspn("abc,def", ",") == 3);
+    memset(buf, 'x', 4);
+    CHECK(memcmp(buf, "xxxx", 4) == 0);
+    memcpy(buf, "0123456789", 11);
+    memmove(buf + 2, buf, 5);
+    CHECK(memcmp(buf, "0101234789", 10) == 0);
+
+    char line[] = "a, b,,c";
+    char *save;
+    CHECK(strcmp(strtok_r(line, ", ", &save), "a") == 0);
+    CHECK(strcmp(strtok_r(NULL, ", ", &save), "b") == 0);
+    CHECK(strcmp(strtok_r(NULL, ", ", &save), "c") == 0);
+    CHECK(strtok_r(NULL, ", ", &save) == NULL);
+
+    char *dup = strdup("copia");
+    CHECK(dup && strcmp(dup, "copia") == 0);
+    free(dup);
+    CHECK(toupper('q') == 'Q' && isdigit('7') && !isalpha('7') && isspace('\n'));
+}
+
+static void test_printf(void) {
+    CHECK_FMT("42|-7|+5| 3", "%d|%i|%+d|% d", 42, -7, 5, 3);
+    CHECK_FMT("00042|   42|42   ", "%05d|%5d|%-5d", 42, 42, 42);
+    CHECK_FMT("ff|FF|0xff|17|0", "%x|%X|%#x|%o|%u", 255, 255, 255, 15, 0);
+    CHECK_FMT("18446744073709551615|-9223372036854775808", "%lu|%lld", (unsigned long)-1, (long long)(-9223372036854775807LL - 1));
+    CHECK_FMT("abc|  abc|ab|x", "%s|%5s|%.2s|%c", "abc", "abc", "abc", 'x');
+    CHECK_FMT("3.14|-0.500|2|1e+06|1.5e-05", "%.2f|%.3f|%.0f|%g|%g", 3.14159, -0.5, 2.4, 1000000.0, 0.000015);
+    CHECK_FMT("   1.25|100%", "%7.2f|%d%%", 1.25, 100);
+    CHECK_FMT("*7   *   7*", "*%-*d*%*d*", 4, 7, 4, 7);
+
+    char small[6];
+    int n = snprintf(small, sizeof small, "%s", "truncated");
+    CHECK(n == 9 && strcmp(small, "trunc") == 0);
+}
+
+static void test_parse(void) {
+    char *end;
+    CHECK(strtol("  -123xyz", &end, 10) == -123 && *end == 'x');
+    CHECK(strtol("0x1F", NULL, 0) == 31 && strtol("017", NULL, 0) == 15);
+    CHECK(strtoul("ffff", NULL, 16) == 0xffff);
+    errno = 0;
+    strtol("99999999999999999999", NULL, 10);
+    CHECK(errno == ERANGE);
+    CHECK(atoi("  77") == 77);
+    CHECK(strtod("2.5e2", NULL) == 250.0 && atof("-0.25") == -0.25);
+
+    int a = 0;
+    unsigned x = 0;
+    char word[16];
+    double d = 0;
+    CHECK(sscanf("12 0x1f hola 3.5", "%d %x %15s %lf", &a, &x, word, &d) == 4);
+    CHECK(a == 12 && x == 31 && strcmp(word, "hola") == 0 && d == 3.5);
+    CHECK(sscanf("w=640,h=480", "w=%d,h=%d", &a, (int *)&x) == 2 && a == 640 && x == 480);
+    CHECK(sscanf("", "%d", &a) == EOF);
+}
+
+static void test_malloc(void) {
+    void *blocks[200];
+    for (int i = 0; i < 200; i++) {
+        blocks[i] = malloc((size_t)(i * 37 % 700) + 1);
+        CHECK(blocks[i] != NULL && ((unsigned long)blocks[i] & 15) == 0);
+        memset(blocks[i], i, (size_t)(i * 37 % 700) + 1);
+    }
+    for (int i = 0; i < 200; i += 2) {
+        free(blocks[i]);
+    }
+    for (int i = 1; i < 200; i += 2) {
+        unsigned char *p = blocks[i];
+        CHECK(p[0] == (unsigned char)i && p[i * 37 % 700] == (unsigned char)i);
+        free(p);
+    }
+
+    char *big = malloc(1 << 20);
+    CHECK(big != NULL);
+    big[0] = 1;
+    big[(1 << 20) - 1] = 2;
+    free(big);
+
+    int *grow = NULL;
+    for (int n = 1; n <= 1000; n++) {
+        grow = realloc(grow, (size_t)n * sizeof(int));
+        CHECK(grow != NULL);
+        grow[n - 1] = n;
+    }
+    CHECK(grow[0] == 1 && grow[999] == 1000);
+    free(grow);
+
+    int *zero = calloc(64, sizeof(int));
+    CHECK(zero && zero[0] == 0 && zero[63] == 0);
+    free(zero);
+
+    int nums[] = {5, 3, 9, 1, 7, 2, 8};
+    qsort(nums, 7, sizeof(int), cmp_int);
+    CHECK(nums[0] == 1 && nums[6] == 9);
+    int key = 7;
+    CHECK(bsearch(&key, nums, 7, sizeof(int), cmp_int) == &nums[4]);
+}
+
+static void test_files(const char *path) {
+    FILE *f = fopen(path, "w");
+    CHECK(f != NULL);
+    if (!f) {
+        return;
+    }
+    for (int i = 0; i < 300; i++) {
+        fprintf(f, "linea %d\n", i);
+    }
+    CHECK(fclose(f) == 0);
+
+    f = fopen(path, "r");
+    CHECK(f != NULL);
+    if (!f) {
+        return;
+    }
+    char line[64];
+    int count = 0;
+    while (fgets(line, sizeof line, f)) {
+        count++;
+    }
+    CHECK(count == 300 && feof(f));
+    CHECK(fseek(f, 0, SEEK_SET) == 0);
+    CHECK(fgets(line, sizeof line, f) && strcmp(line, "linea 0\n") == 0);
+    CHECK(ftell(f) == 8);
+    int c = fgetc(f);
+    CHECK(c == 'l' && ungetc(c, f) == 'l' && fgetc(f) == 'l');
+    fclose(f);
+
+    f = fopen(path, "a");
+    CHECK(f && fputs("fin\n", f) == 0);
+    fclose(f);
+    f = fopen(path, "r");
+    CHECK(f && fseek(f, -4, SEEK_END) == 0 && fgets(line, sizeof line, f) && strcmp(line, "fin\n") == 0);
+    fclose(f);
+
+    CHECK(fopen("/no/existe/nada.txt", "r") == NULL && errno == ENOENT);
+    remove(path);
+}
+
+int main(int argc, char **argv) {
+    const char *path = argc > 1 ? argv[1] : "LIBCTEST.TXT";
+    test_string();
+    test_printf();
+    test_parse();
+    test_malloc();
+    test_files(path);
+    assert(failures >= 0);
+    if (failures) {
+        printf("redux-libc: %d fallos\n", failures);
+        return 1;
+    }
+    printf("redux-libc: todas las pruebas OK\n");
+    return 0;
+}
@@ -0,0 +1,272 @@
+//! `cargo redux`: build helper for redux-sdk apps.
+//!
+//! ```text
+//! cargo redux build [--release] [--bin NAME] [--out FILE] [-- CARGO_ARGS...]
+//! cargo redux doctor FILE
+//! cargo redux new NAME
+//! ```
+//!
+//! `build` runs `cargo +nightly build` for the SDK target spec with
+//! `-Z build-std`, checks the ELF profile the ReduxOS loader accepts
+//! (x86_64 `ET_EXEC`, no `PT_INTERP`, no `PT_DYNAMIC`) and copies the binary
+//! to `build/redux/NAME.BIN`, an 8.3 name the FAT volume keeps as is.
+//! `REDUX_TOOLCHAIN` picks another nightly toolchain.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command};
+
+const TARGET_NAME: &str = "x86_64-redux";
+const TARGET_SPEC: &str = include_str!("../../x86_64-redux.json");
+
+const PT_DYNAMIC: u32 = 2;
+const PT_INTERP: u32 = 3;
+const ET_EXEC: u16 = 2;
+const EM_X86_64: u16 = 62;
+
+const USAGE: &str = "uso:
+  cargo redux build [--release] [--bin NOMBRE] [--out ARCHIVO] [-- ARGS_CARGO...]
+  cargo redux doctor ARCHIVO
+  cargo redux new NOMBRE";
+
+struct BuildArgs {
+    release: bool,
+    bin: Option<String>,
+    out: Option<PathBuf>,
+    cargo_args: Vec<String>,
+}
+
+fn parse_build_args(args: &[String]) -> Result<BuildArgs, String> {
+    let mut parsed = BuildArgs {
+        release: false,
+        bin: None,
+        out: None,
+        cargo_args: Vec::new(),
+    };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--release" => parsed.release = true,
+            "--bin" => parsed.bin = Some(iter.next().ok_or("--bin necesita un nombre")?.clone()),
+            "--out" => parsed.out = Some(PathBuf::from(iter.next().ok_or("--out necesita un archivo")?)),
+            "--" => parsed.cargo_args.extend(iter.by_ref().cloned()),
+            other => return Err(format!("opcion desconocida: {}", other)),
+        }
+    }
+    Ok(parsed)
+}
+
+/// `name = "..."` of the `[package]` table.
+fn package_name(manifest: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in manifest.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+            continue;
+        }
+        if !in_package {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            if key.trim() == "name" {
+                return Some(value.trim().trim_matches('"').to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Up to 8 letters and digits of `name` in uppercase, plus `.BIN`.
+fn volume_file_name(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .take(8)
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    format!("{}.BIN", if stem.is_empty() { "APP" } else { stem.as_str() })
+}
+
+fn u16_at(data: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([data[at], data[at + 1]])
+}
+
+fn u32_at(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(data[at..at + 8].try_into().unwrap())
+}
+
+/// Why `data` would not load as a ReduxOS app, if it would not.
+fn check_elf(data: &[u8]) -> Result<(), String> {
+    if data.len() < 64 || &data[..4] != b"\x7fELF" {
+        return Err("no es un ELF".into());
+    }
+    if data[4] != 2 || data[5] != 1 {
+        return Err("se esperaba ELF64 little endian".into());
+    }
+    if u16_at(data, 18) != EM_X86_64 {
+        return Err("se esperaba x86_64".into());
+    }
+    if u16_at(data, 16) != ET_EXEC {
+        return Err("se esperaba ET_EXEC (sin PIE)".into());
+    }
+    let phoff = u64_at(data, 32) as usize;
+    let phentsize = u16_at(data, 54) as usize;
+    let phnum = u16_at(data, 56) as usize;
+    for i in 0..phnum {
+        let at = phoff + i * phentsize;
+        if at + 4 > data.len() {
+            return Err("tabla de program headers truncada".into());
+        }
+        match u32_at(data, at) {
+            PT_INTERP => return Err("tiene PT_INTERP; se esperaba un ELF estatico".into()),
+            PT_DYNAMIC => return Err("tiene PT_DYNAMIC; se esperaba un ELF estatico".into()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn doctor(path: &Path) -> Result<(), String> {
+    let data = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    check_elf(&data).map_err(|e| format!("{}: {}", path.display(), e))?;
+    println!("[redux] {}: ELF64 x86_64 estatico, ET_EXEC: compatible", path.display());
+    Ok(())
+}
+
+fn build(args: &[String]) -> Result<(), String> {
+    let args = parse_build_args(args)?;
+    let manifest = fs::read_to_string("Cargo.toml").map_err(|_| "no hay Cargo.toml en este directorio".to_string())?;
+    let name = match args.bin.clone().or_else(|| package_name(&manifest)) {
+        Some(name) => name,
+        None => return Err("no se encontro el nombre del paquete".into()),
+    };
+    let target_dir = PathBuf::from(env::var("CARGO_TARGET_DIR").unwrap_or_else(|_| "target".into()));
+    let spec = target_dir.join(format!("{}.json", TARGET_NAME));
+    fs::create_dir_all(&target_dir).map_err(|e| e.to_string())?;
+    fs::write(&spec, TARGET_SPEC).map_err(|e| e.to_string())?;
+
+    let toolchain = env::var("REDUX_TOOLCHAIN").unwrap_or_else(|_| "nightly".into());
+    let mut cargo = Command::new("cargo");
+    cargo
+        .arg(format!("+{}", toolchain))
+        .args(["build", "-Z", "json-target-spec", "-Z", "build-std=core,alloc"])
+        .args(["-Z", "build-std-features=compiler-builtins-mem", "--target"])
+        .arg(&spec);
+    if args.release {
+        cargo.arg("--release");
+    }
+    if let Some(bin) = &args.bin {
+        cargo.args(["--bin", bin]);
+    }
+    cargo.args(&args.cargo_args);
+    println!("[redux] compilando {} para {}...", name, TARGET_NAME);
+    let status = cargo
+        .status()
+        .map_err(|e| format!("no se pudo ejecutar cargo: {}", e))?;
+    if !status.success() {
+        return Err("cargo build fallo (hace falta `rustup component add rust-src --toolchain nightly`)".into());
+    }
+
+    let profile = if args.release { "release" } else { "debug" };
+    let built = target_dir.join(TARGET_NAME).join(profile).join(&name);
+    doctor(&built)?;
+    let out = args
+        .out
+        .unwrap_or_else(|| Path::new("build").join("redux").join(volume_file_name(&name)));
+    if let Some(parent) = out.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::copy(&built, &out).map_err(|e| format!("{}: {}", out.display(), e))?;
+    let file = out.file_name().and_then(|f| f.to_str()).unwrap_or("APP.BIN");
+    println!("[redux] OK -> {}", out.display());
+    println!(
+        "[redux] en ReduxOS: linux inspect /{} ; linux runloop start /{}",
+        file, file
+    );
+    Ok(())
+}
+
+fn new_project(name: &str) -> Result<(), String> {
+    let dir = Path::new(name);
+    if dir.exists() {
+        return Err(format!("{} ya existe", name));
+    }
+    let sdk = match env::var("REDUX_SDK_PATH") {
+        Ok(path) => format!("{{ path = \"{}\" }}", path),
+        Err(_) => "\"0.1\"".into(),
+    };
+    let manifest = format!(
+        "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\nredux-sdk = {sdk}\n\n\
+         [profile.dev]\npanic = \"abort\"\n\n[profile.release]\npanic = \"abort\"\nopt-level = \"s\"\nlto = true\n"
+    );
+    let main = "#![no_std]\n#![no_main]\n\nuse redux_sdk::println;\n\nredux_sdk::main!(main);\n\n\
+                fn main() {\n    println!(\"hola desde ReduxOS\");\n}\n";
+    fs::create_dir_all(dir.join("src")).map_err(|e| e.to_string())?;
+    fs::write(dir.join("Cargo.toml"), manifest).map_err(|e| e.to_string())?;
+    fs::write(dir.join("src").join("main.rs"), main).map_err(|e| e.to_string())?;
+    println!("[redux] creado {}; compila con: cd {} && cargo redux build", name, name);
+    Ok(())
+}
+
+fn main() {
+    // Cargo runs `cargo-redux redux <args>`; `cargo-redux <args>` works too.
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("redux") {
+        args.remove(0);
+    }
+    let result = match args.first().map(String::as_str) {
+        Some("build") => build(&args[1..]),
+        Some("doctor") if args.len() == 2 => doctor(Path::new(&args[1])),
+        Some("new") if args.len() == 2 => new_project(&args[1]),
+        _ => Err(USAGE.into()),
+    };
+    if let Err(message) = result {
+        eprintln!("[redux] error: {}", message);
+        exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elf(e_type: u16, program_headers: &[u32]) -> Vec<u8> {
+        let mut data = vec![0u8; 64 + 56 * program_headers.len()];
+        data[..4].copy_from_slice(b"\x7fELF");
+        data[4] = 2;
+        data[5] = 1;
+        data[16..18].copy_from_slice(&e_type.to_le_bytes());
+        data[18..20].copy_from_slice(&EM_X86_64.to_le_bytes());
+        data[32..40].copy_from_slice(&64u64.to_le_bytes());
+        data[54..56].copy_from_slice(&56u16.to_le_bytes());
+        data[56..58].copy_from_slice(&(program_headers.len() as u16).to_le_bytes());
+        for (i, kind) in program_headers.iter().enumerate() {
+            let at = 64 + i * 56;
+            data[at..at + 4].copy_from_slice(&kind.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn elf_profile() {
+        assert_eq!(check_elf(&elf(ET_EXEC, &[1, 1])), Ok(()));
+        assert!(check_elf(&elf(3, &[1])).is_err());
+        assert!(check_elf(&elf(ET_EXEC, &[1, PT_INTERP])).is_err());
+        assert!(check_elf(&elf(ET_EXEC, &[PT_DYNAMIC])).is_err());
+        assert!(check_elf(b"MZ").is_err());
+    }
+
+    #[test]
+    fn names() {
+        let manifest = "[package]\nname = \"rust_paint\"\n\n[dependencies]\nname = \"x\"\n";
+        assert_eq!(package_name(manifest).as_deref(), Some("rust_paint"));
+        assert_eq!(volume_file_name("rust_paint"), "RUSTPAIN.BIN");
+        assert_eq!(volume_file_name("_"), "APP.BIN");
+    }
+}
@@ -0,0 +1,62 @@
+//! The system config registry (`config get/set` in the shell).
+//!
+//! Keys under `system.` are read-only for apps.
+
+use alloc::string::String;
+use alloc::vec;
+
+use crate::sys::{self, Error};
+
+pub fn get(key: &str) -> crate::Result<Option<String>> {
+    let mut buf = vec![0u8; 256];
+    loop {
+        let len = match unsafe {
+            sys::native(
+                sys::native::CONFIG_GET,
+                key.as_ptr() as usize,
+                key.len(),
+                buf.as_mut_ptr() as usize,
+                buf.len(),
+            )
+        } {
+            Ok(len) => len as usize,
+            Err(Error::NotFound) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if len <= buf.len() {
+            buf.truncate(len);
+            return String::from_utf8(buf).map(Some).map_err(|_| Error::Protocol);
+        }
+        buf.resize(len, 0);
+    }
+}
+
+/// Stores `value`, typed like `config set` does (numbers, booleans, text).
+pub fn set(key: &str, value: &str) -> crate::Result<()> {
+    if value.is_empty() {
+        return Err(Error::Invalid);
+    }
+    unsafe {
+        sys::native(
+            sys::native::CONFIG_SET,
+            key.as_ptr() as usize,
+            key.len(),
+            value.as_ptr() as usize,
+            value.len(),
+        )?;
+    }
+    Ok(())
+}
+
+pub fn unset(key: &str) -> crate::Result<()> {
+    unsafe {
+        sys::native(sys::native::CONFIG_SET, key.as_ptr() as usize, key.len(), 0, 0)?;
+    }
+    Ok(())
+}
+
+/// Sequence number of the newest change under `prefix`; poll it and compare
+/// with the last value seen.
+pub fn revision(prefix: &str) -> crate::Result<u64> {
+    unsafe { sys::native(sys::native::CONFIG_WATCH, prefix.as_ptr() as usize, prefix.len(), 0, 0) }
+}
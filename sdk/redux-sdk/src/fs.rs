@@ -0,0 +1,377 @@
+//! Files, directories and folder watches.
+//!
+//! Paths are the ones the shell uses: `/` is the root of the mounted volume.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::sys::{self, nr, Error};
+
+const AT_FDCWD: isize = -100;
+const O_RDONLY: usize = 0;
+const O_WRONLY: usize = 0o1;
+const O_RDWR: usize = 0o2;
+const O_CREAT: usize = 0o100;
+const O_TRUNC: usize = 0o1000;
+const O_APPEND: usize = 0o2000;
+const O_DIRECTORY: usize = 0o200000;
+const O_CLOEXEC: usize = 0o2000000;
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const DT_DIR: u8 = 4;
+
+/// NUL-terminated copy of `path`.
+fn c_path(path: &str) -> crate::Result<Vec<u8>> {
+    if path.as_bytes().contains(&0) {
+        return Err(Error::Invalid);
+    }
+    let mut out = Vec::with_capacity(path.len() + 1);
+    out.extend_from_slice(path.as_bytes());
+    out.push(0);
+    Ok(out)
+}
+
+fn open_at(path: &str, flags: usize) -> crate::Result<i32> {
+    let path = c_path(path)?;
+    let ret = unsafe {
+        sys::syscall4(
+            nr::OPENAT,
+            AT_FDCWD as usize,
+            path.as_ptr() as usize,
+            flags | O_CLOEXEC,
+            0o644,
+        )
+    };
+    sys::check(ret).map(|fd| fd as i32)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeekFrom {
+    Start(u64),
+    End(i64),
+    Current(i64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Metadata {
+    pub len: u64,
+    pub is_dir: bool,
+}
+
+impl Metadata {
+    /// From a Linux `struct stat`: `st_mode` at 24, `st_size` at 48.
+    fn from_stat(stat: &[u8; 144]) -> Self {
+        let mode = u32::from_le_bytes([stat[24], stat[25], stat[26], stat[27]]);
+        let mut size = [0u8; 8];
+        size.copy_from_slice(&stat[48..56]);
+        Self {
+            len: i64::from_le_bytes(size).max(0) as u64,
+            is_dir: mode & S_IFMT == S_IFDIR,
+        }
+    }
+}
+
+/// An open file; closed on drop.
+#[derive(Debug)]
+pub struct File {
+    fd: i32,
+}
+
+impl File {
+    pub fn open(path: &str) -> crate::Result<File> {
+        open_at(path, O_RDONLY).map(|fd| File { fd })
+    }
+
+    /// Opens for writing, creating the file or emptying it.
+    pub fn create(path: &str) -> crate::Result<File> {
+        open_at(path, O_WRONLY | O_CREAT | O_TRUNC).map(|fd| File { fd })
+    }
+
+    /// Opens for writing at the end, creating the file if needed.
+    pub fn append(path: &str) -> crate::Result<File> {
+        open_at(path, O_WRONLY | O_CREAT | O_APPEND).map(|fd| File { fd })
+    }
+
+    pub fn open_rw(path: &str) -> crate::Result<File> {
+        open_at(path, O_RDWR).map(|fd| File { fd })
+    }
+
+    pub fn fd(&self) -> i32 {
+        self.fd
+    }
+
+    pub fn read(&mut self, buf: &mut [u8]) -> crate::Result<usize> {
+        crate::io::read(self.fd, buf)
+    }
+
+    pub fn read_to_end(&mut self, out: &mut Vec<u8>) -> crate::Result<usize> {
+        let start = out.len();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let n = self.read(&mut chunk)?;
+            if n == 0 {
+                return Ok(out.len() - start);
+            }
+            out.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    pub fn write_all(&mut self, data: &[u8]) -> crate::Result<()> {
+        crate::io::write_all(self.fd, data)
+    }
+
+    /// Moves the file offset; returns the new offset.
+    pub fn seek(&mut self, pos: SeekFrom) -> crate::Result<u64> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(n) => (n as i64, 0),
+            SeekFrom::Current(n) => (n, 1),
+            SeekFrom::End(n) => (n, 2),
+        };
+        let ret = unsafe { sys::syscall3(nr::LSEEK, self.fd as usize, offset as usize, whence) };
+        sys::check(ret).map(|n| n as u64)
+    }
+
+    pub fn metadata(&self) -> crate::Result<Metadata> {
+        let mut stat = [0u8; 144];
+        let ret = unsafe { sys::syscall2(nr::FSTAT, self.fd as usize, stat.as_mut_ptr() as usize) };
+        sys::check(ret)?;
+        Ok(Metadata::from_stat(&stat))
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        unsafe {
+            sys::syscall1(nr::CLOSE, self.fd as usize);
+        }
+    }
+}
+
+pub fn read(path: &str) -> crate::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    File::open(path)?.read_to_end(&mut out)?;
+    Ok(out)
+}
+
+pub fn read_to_string(path: &str) -> crate::Result<String> {
+    String::from_utf8(read(path)?).map_err(|_| Error::Invalid)
+}
+
+/// Replaces the contents of `path` with `data`.
+pub fn write(path: &str, data: &[u8]) -> crate::Result<()> {
+    File::create(path)?.write_all(data)
+}
+
+pub fn metadata(path: &str) -> crate::Result<Metadata> {
+    let c = c_path(path)?;
+    let mut stat = [0u8; 144];
+    let ret = unsafe {
+        sys::syscall4(
+            nr::NEWFSTATAT,
+            AT_FDCWD as usize,
+            c.as_ptr() as usize,
+            stat.as_mut_ptr() as usize,
+            0,
+        )
+    };
+    sys::check(ret)?;
+    Ok(Metadata::from_stat(&stat))
+}
+
+pub fn exists(path: &str) -> bool {
+    metadata(path).is_ok()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// Entries of directory `path`, without `.` and `..`.
+pub fn read_dir(path: &str) -> crate::Result<Vec<DirEntry>> {
+    let dir = File {
+        fd: open_at(path, O_RDONLY | O_DIRECTORY)?,
+    };
+    let mut entries = Vec::new();
+    let mut buf = [0u8; 2048];
+    loop {
+        let ret = unsafe { sys::syscall3(nr::GETDENTS64, dir.fd as usize, buf.as_mut_ptr() as usize, buf.len()) };
+        let n = sys::check(ret)?;
+        if n == 0 {
+            return Ok(entries);
+        }
+        parse_dirents(&buf[..n], &mut entries)?;
+    }
+}
+
+/// `struct linux_dirent64` records: ino u64, off i64, reclen u16, type u8,
+/// NUL-terminated name.
+fn parse_dirents(mut buf: &[u8], out: &mut Vec<DirEntry>) -> crate::Result<()> {
+    while buf.len() >= 19 {
+        let reclen = u16::from_le_bytes([buf[16], buf[17]]) as usize;
+        if reclen < 19 || reclen > buf.len() {
+            return Err(Error::Protocol);
+        }
+        let raw_name = &buf[19..reclen];
+        let name_len = raw_name.iter().position(|&b| b == 0).unwrap_or(raw_name.len());
+        let name = String::from_utf8_lossy(&raw_name[..name_len]).into_owned();
+        if name != "." && name != ".." {
+            out.push(DirEntry {
+                name,
+                is_dir: buf[18] == DT_DIR,
+            });
+        }
+        buf = &buf[reclen..];
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Created,
+    Written,
+    Removed,
+    MovedFrom,
+    MovedTo,
+    /// Events were dropped; rescan the folder.
+    Overflow,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WatchEvent {
+    pub change: Change,
+    pub name: String,
+    /// Pairs a `MovedFrom` with its `MovedTo`.
+    pub cookie: u32,
+}
+
+/// Change notifications for one folder (and what is below it); removed on
+/// drop.
+#[derive(Debug)]
+pub struct Watch {
+    id: u64,
+}
+
+impl Watch {
+    /// `path` is a folder from the volume root; `""` or `/` watches it all.
+    pub fn new(path: &str) -> crate::Result<Watch> {
+        let id = unsafe { sys::native(sys::native::FS_WATCH, path.as_ptr() as usize, path.len(), 0, 0)? };
+        Ok(Watch { id })
+    }
+
+    /// Events queued since the last call; empty when there are none.
+    pub fn events(&self) -> crate::Result<Vec<WatchEvent>> {
+        let mut buf = [0u8; 4096];
+        let n = unsafe {
+            sys::native(
+                sys::native::FS_READ_EVENTS,
+                self.id as usize,
+                buf.as_mut_ptr() as usize,
+                buf.len(),
+                0,
+            )?
+        };
+        parse_watch_records(&buf[..n as usize])
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        let _ = unsafe { sys::native(sys::native::FS_UNWATCH, self.id as usize, 0, 0, 0) };
+    }
+}
+
+/// `[u8 change][u8 0][u16 name length][u32 cookie][name]`, little endian.
+fn parse_watch_records(mut buf: &[u8]) -> crate::Result<Vec<WatchEvent>> {
+    let mut out = Vec::new();
+    while !buf.is_empty() {
+        if buf.len() < 8 {
+            return Err(Error::Protocol);
+        }
+        let change = match buf[0] {
+            1 => Change::Created,
+            2 => Change::Written,
+            3 => Change::Removed,
+            4 => Change::MovedFrom,
+            5 => Change::MovedTo,
+            6 => Change::Overflow,
+            _ => return Err(Error::Protocol),
+        };
+        let name_len = u16::from_le_bytes([buf[2], buf[3]]) as usize;
+        let cookie = u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]);
+        let name = buf.get(8..8 + name_len).ok_or(Error::Protocol)?;
+        out.push(WatchEvent {
+            change,
+            name: String::from_utf8_lossy(name).into_owned(),
+            cookie,
+        });
+        buf = &buf[8 + name_len..];
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dirent(name: &str, kind: u8) -> Vec<u8> {
+        let reclen = (19 + name.len() + 1 + 7) & !7;
+        let mut rec = vec![0u8; reclen];
+        rec[16..18].copy_from_slice(&(reclen as u16).to_le_bytes());
+        rec[18] = kind;
+        rec[19..19 + name.len()].copy_from_slice(name.as_bytes());
+        rec
+    }
+
+    #[test]
+    fn dirents_skip_dot_entries() {
+        let mut buf = dirent(".", DT_DIR);
+        buf.extend(dirent("..", DT_DIR));
+        buf.extend(dirent("DOCS", DT_DIR));
+        buf.extend(dirent("NOTA.TXT", 8));
+        let mut entries = Vec::new();
+        parse_dirents(&buf, &mut entries).unwrap();
+        assert_eq!(
+            entries,
+            [
+                DirEntry {
+                    name: "DOCS".into(),
+                    is_dir: true
+                },
+                DirEntry {
+                    name: "NOTA.TXT".into(),
+                    is_dir: false
+                },
+            ]
+        );
+        assert_eq!(parse_dirents(&buf[..20], &mut entries), Err(Error::Protocol));
+    }
+
+    #[test]
+    fn watch_records_decode() {
+        let mut buf = vec![4, 0, 3, 0, 9, 0, 0, 0];
+        buf.extend_from_slice(b"A.T");
+        buf.extend_from_slice(&[5, 0, 3, 0, 9, 0, 0, 0]);
+        buf.extend_from_slice(b"B.T");
+        let events = parse_watch_records(&buf).unwrap();
+        assert_eq!(events[0].change, Change::MovedFrom);
+        assert_eq!(events[1].name, "B.T");
+        assert_eq!(events[1].cookie, 9);
+        assert_eq!(parse_watch_records(&buf[..10]), Err(Error::Protocol));
+    }
+
+    #[test]
+    fn stat_fields() {
+        let mut stat = [0u8; 144];
+        stat[24..28].copy_from_slice(&(S_IFDIR | 0o755).to_le_bytes());
+        stat[48..56].copy_from_slice(&4096i64.to_le_bytes());
+        assert_eq!(
+            Metadata::from_stat(&stat),
+            Metadata {
+                len: 4096,
+                is_dir: true
+            }
+        );
+    }
+}
@@ -0,0 +1,545 @@
+//! Windows through the X11 subset the kernel serves.
+//!
+//! The Linux shim accepts X11 clients on TCP `127.0.0.1:6000 + N` for
+//! display `:N` (`$DISPLAY`, `:0` when unset) and draws their windows into
+//! the desktop. This module speaks the part of the core protocol an app
+//! needs: windows with a title, filled rectangles, 32-bit pixel blits and
+//! the input and structure events. Colors are `0xRRGGBB`.
+//!
+//! Input goes to the focused window, so key, button and motion events carry
+//! no window id. Key codes are the server's; on ReduxOS [`key_char`] turns
+//! them back into the character that was typed.
+
+use alloc::vec::Vec;
+
+use crate::sys::{self, nr, Error};
+
+const X11_TCP_PORT_BASE: u16 = 6000;
+const AF_INET: usize = 2;
+const SOCK_STREAM: usize = 1;
+const POLLIN: i16 = 1;
+
+const OP_CREATE_WINDOW: u8 = 1;
+const OP_DESTROY_WINDOW: u8 = 4;
+const OP_MAP_WINDOW: u8 = 8;
+const OP_CHANGE_PROPERTY: u8 = 18;
+const OP_CREATE_GC: u8 = 55;
+const OP_CHANGE_GC: u8 = 56;
+const OP_FREE_GC: u8 = 60;
+const OP_POLY_FILL_RECTANGLE: u8 = 70;
+const OP_PUT_IMAGE: u8 = 72;
+
+const ATOM_STRING: u32 = 31;
+const ATOM_WM_NAME: u32 = 39;
+const CW_BACK_PIXEL: u32 = 0x0002;
+const CW_EVENT_MASK: u32 = 0x0800;
+const GC_FOREGROUND: u32 = 0x0004;
+const IMAGE_Z_PIXMAP: u8 = 2;
+const WINDOW_EVENTS: u32 = (1 << 0) // KeyPress
+    | (1 << 1) // KeyRelease
+    | (1 << 2) // ButtonPress
+    | (1 << 3) // ButtonRelease
+    | (1 << 6) // PointerMotion
+    | (1 << 15) // Exposure
+    | (1 << 17); // StructureNotify
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Part of the window needs repainting.
+    Expose {
+        window: u32,
+    },
+    Key {
+        keycode: u8,
+        pressed: bool,
+    },
+    Button {
+        button: u8,
+        x: i16,
+        y: i16,
+        pressed: bool,
+    },
+    Motion {
+        x: i16,
+        y: i16,
+    },
+    Resize {
+        window: u32,
+        width: u16,
+        height: u16,
+    },
+    /// The window is gone (closed from the desktop or destroyed).
+    Closed {
+        window: u32,
+    },
+    /// A request failed; `major` is its opcode.
+    Error {
+        code: u8,
+        major: u8,
+    },
+}
+
+/// Character typed for `keycode` on the ReduxOS server, which sends ASCII
+/// plus 8.
+pub fn key_char(keycode: u8) -> Option<char> {
+    let ascii = keycode.checked_sub(8)?;
+    (ascii < 0x80).then_some(ascii as char)
+}
+
+#[derive(Debug)]
+pub struct Window {
+    pub id: u32,
+    gc: u32,
+    pub width: u16,
+    pub height: u16,
+}
+
+#[derive(Debug)]
+struct Setup {
+    id_base: u32,
+    id_mask: u32,
+    max_request_bytes: usize,
+    root: u32,
+    width: u16,
+    height: u16,
+}
+
+/// One little-endian request; `finish` writes the length field.
+struct Req(Vec<u8>);
+
+impl Req {
+    fn new(opcode: u8, data: u8) -> Req {
+        Req(alloc::vec![opcode, data, 0, 0])
+    }
+
+    fn u16(mut self, value: u16) -> Req {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn i16(self, value: i16) -> Req {
+        self.u16(value as u16)
+    }
+
+    fn u32(mut self, value: u32) -> Req {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn bytes(mut self, data: &[u8]) -> Req {
+        self.0.extend_from_slice(data);
+        while !self.0.len().is_multiple_of(4) {
+            self.0.push(0);
+        }
+        self
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let units = (self.0.len() / 4) as u16;
+        self.0[2..4].copy_from_slice(&units.to_le_bytes());
+        self.0
+    }
+}
+
+fn u16_at(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([buf[at], buf[at + 1]])
+}
+
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+/// Connection setup, little endian, no authorization.
+fn setup_request() -> [u8; 12] {
+    [b'l', 0, 11, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+}
+
+/// Setup reply: 8-byte header, 32 bytes of connection data, vendor, pixmap
+/// formats, then the first screen.
+fn parse_setup(reply: &[u8]) -> crate::Result<Setup> {
+    if reply.len() < 40 || reply[0] != 1 {
+        return Err(Error::Protocol);
+    }
+    let vendor_len = u16_at(reply, 24) as usize;
+    let formats = reply[29] as usize;
+    let screen = 40 + ((vendor_len + 3) & !3) + formats * 8;
+    if reply.len() < screen + 40 {
+        return Err(Error::Protocol);
+    }
+    Ok(Setup {
+        id_base: u32_at(reply, 12),
+        id_mask: u32_at(reply, 16),
+        max_request_bytes: u16_at(reply, 26) as usize * 4,
+        root: u32_at(reply, screen),
+        width: u16_at(reply, screen + 20),
+        height: u16_at(reply, screen + 22),
+    })
+}
+
+/// Bytes the server unit at the front of `buf` takes: 32, plus the extra
+/// length of replies and generic events. `None` until it is all there.
+fn unit_len(buf: &[u8]) -> Option<usize> {
+    if buf.len() < 32 {
+        return None;
+    }
+    let len = match buf[0] & 0x7F {
+        1 | 35 => 32 + u32_at(buf, 4) as usize * 4,
+        _ => 32,
+    };
+    (buf.len() >= len).then_some(len)
+}
+
+fn decode_event(unit: &[u8]) -> Option<Event> {
+    let pressed = matches!(unit[0] & 0x7F, 2 | 4);
+    match unit[0] & 0x7F {
+        0 => Some(Event::Error {
+            code: unit[1],
+            major: unit[10],
+        }),
+        2 | 3 => Some(Event::Key {
+            keycode: unit[1],
+            pressed,
+        }),
+        4 | 5 => Some(Event::Button {
+            button: unit[1],
+            x: u16_at(unit, 20) as i16,
+            y: u16_at(unit, 22) as i16,
+            pressed,
+        }),
+        6 => Some(Event::Motion {
+            x: u16_at(unit, 20) as i16,
+            y: u16_at(unit, 22) as i16,
+        }),
+        12 => Some(Event::Expose {
+            window: u32_at(unit, 4),
+        }),
+        17 => Some(Event::Closed {
+            window: u32_at(unit, 8),
+        }),
+        // The ReduxOS server leaves out the above-sibling field, so the
+        // geometry starts at 12.
+        22 => Some(Event::Resize {
+            window: u32_at(unit, 8),
+            width: u16_at(unit, 16),
+            height: u16_at(unit, 18),
+        }),
+        _ => None,
+    }
+}
+
+/// A connection to the display server; closed on drop.
+#[derive(Debug)]
+pub struct Display {
+    fd: i32,
+    setup: Setup,
+    next_id: u32,
+    rx: Vec<u8>,
+}
+
+impl Display {
+    /// Connects to `$DISPLAY` (`:0` when unset).
+    pub fn connect() -> crate::Result<Display> {
+        let number = crate::rt::env("DISPLAY")
+            .and_then(|d| d.trim_start_matches(':').split('.').next())
+            .and_then(|n| n.parse::<u16>().ok())
+            .unwrap_or(0);
+        let fd = sys::check(unsafe { sys::syscall3(nr::SOCKET, AF_INET, SOCK_STREAM, 0) })? as i32;
+        let mut display = Display {
+            fd,
+            setup: Setup {
+                id_base: 0,
+                id_mask: 0,
+                max_request_bytes: 0,
+                root: 0,
+                width: 0,
+                height: 0,
+            },
+            next_id: 0,
+            rx: Vec::new(),
+        };
+        let port = X11_TCP_PORT_BASE.saturating_add(number).to_be_bytes();
+        let addr: [u8; 16] = [AF_INET as u8, 0, port[0], port[1], 127, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0];
+        sys::check(unsafe { sys::syscall3(nr::CONNECT, fd as usize, addr.as_ptr() as usize, addr.len()) })?;
+        crate::io::write_all(fd, &setup_request())?;
+        display.fill_rx(8)?;
+        let total = 8 + u16_at(&display.rx, 6) as usize * 4;
+        display.fill_rx(total)?;
+        display.setup = parse_setup(&display.rx[..total])?;
+        display.rx.drain(..total);
+        Ok(display)
+    }
+
+    /// Screen size in pixels.
+    pub fn size(&self) -> (u16, u16) {
+        (self.setup.width, self.setup.height)
+    }
+
+    fn fill_rx(&mut self, len: usize) -> crate::Result<()> {
+        let mut chunk = [0u8; 4096];
+        while self.rx.len() < len {
+            let n = crate::io::read(self.fd, &mut chunk)?;
+            if n == 0 {
+                return Err(Error::Protocol);
+            }
+            self.rx.extend_from_slice(&chunk[..n]);
+        }
+        Ok(())
+    }
+
+    fn alloc_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.setup.id_base | (self.next_id & self.setup.id_mask)
+    }
+
+    fn send(&mut self, req: Req) -> crate::Result<()> {
+        crate::io::write_all(self.fd, &req.finish())
+    }
+
+    /// Creates and maps a `width` x `height` window.
+    pub fn create_window(&mut self, title: &str, width: u16, height: u16, background: u32) -> crate::Result<Window> {
+        let id = self.alloc_id();
+        let gc = self.alloc_id();
+        let root = self.setup.root;
+        self.send(
+            Req::new(OP_CREATE_WINDOW, 0)
+                .u32(id)
+                .u32(root)
+                .i16(0)
+                .i16(0)
+                .u16(width.max(1))
+                .u16(height.max(1))
+                .u16(0) // border
+                .u16(1) // InputOutput
+                .u32(0) // CopyFromParent visual
+                .u32(CW_BACK_PIXEL | CW_EVENT_MASK)
+                .u32(background)
+                .u32(WINDOW_EVENTS),
+        )?;
+        self.send(
+            Req::new(OP_CREATE_GC, 0)
+                .u32(gc)
+                .u32(id)
+                .u32(GC_FOREGROUND)
+                .u32(background),
+        )?;
+        let window = Window {
+            id,
+            gc,
+            width: width.max(1),
+            height: height.max(1),
+        };
+        self.set_title(&window, title)?;
+        self.send(Req::new(OP_MAP_WINDOW, 0).u32(id))?;
+        Ok(window)
+    }
+
+    pub fn set_title(&mut self, window: &Window, title: &str) -> crate::Result<()> {
+        self.send(
+            Req::new(OP_CHANGE_PROPERTY, 0) // Replace
+                .u32(window.id)
+                .u32(ATOM_WM_NAME)
+                .u32(ATOM_STRING)
+                .u32(8) // format, then 3 bytes of padding
+                .u32(title.len() as u32)
+                .bytes(title.as_bytes()),
+        )
+    }
+
+    pub fn fill_rect(
+        &mut self,
+        window: &Window,
+        x: i16,
+        y: i16,
+        width: u16,
+        height: u16,
+        color: u32,
+    ) -> crate::Result<()> {
+        self.send(Req::new(OP_CHANGE_GC, 0).u32(window.gc).u32(GC_FOREGROUND).u32(color))?;
+        self.send(
+            Req::new(OP_POLY_FILL_RECTANGLE, 0)
+                .u32(window.id)
+                .u32(window.gc)
+                .i16(x)
+                .i16(y)
+                .u16(width)
+                .u16(height),
+        )
+    }
+
+    pub fn clear(&mut self, window: &Window, color: u32) -> crate::Result<()> {
+        let (width, height) = (window.width, window.height);
+        self.fill_rect(window, 0, 0, width, height, color)
+    }
+
+    /// Copies `width` x `height` pixels (`0xRRGGBB`, row after row) to
+    /// `x, y`, split into as many requests as the server's size limit asks.
+    pub fn put_pixels(
+        &mut self,
+        window: &Window,
+        x: i16,
+        y: i16,
+        width: u16,
+        height: u16,
+        pixels: &[u32],
+    ) -> crate::Result<()> {
+        let row = width as usize;
+        if row == 0 || pixels.len() < row * height as usize {
+            return Err(Error::Invalid);
+        }
+        let rows_per_request = (self.setup.max_request_bytes.saturating_sub(24) / (row * 4)).max(1);
+        let mut top = 0usize;
+        while top < height as usize {
+            let rows = rows_per_request.min(height as usize - top);
+            let mut data = Vec::with_capacity(rows * row * 4);
+            for pixel in &pixels[top * row..(top + rows) * row] {
+                data.extend_from_slice(&pixel.to_le_bytes());
+            }
+            self.send(
+                Req::new(OP_PUT_IMAGE, IMAGE_Z_PIXMAP)
+                    .u32(window.id)
+                    .u32(window.gc)
+                    .u16(width)
+                    .u16(rows as u16)
+                    .i16(x)
+                    .i16(y.saturating_add(top as i16))
+                    .u32(24 << 8) // left pad 0, depth 24
+                    .bytes(&data),
+            )?;
+            top += rows;
+        }
+        Ok(())
+    }
+
+    pub fn destroy_window(&mut self, window: Window) -> crate::Result<()> {
+        self.send(Req::new(OP_FREE_GC, 0).u32(window.gc))?;
+        self.send(Req::new(OP_DESTROY_WINDOW, 0).u32(window.id))
+    }
+
+    /// Next event. Waits up to `timeout_ms` (forever with `None`) and
+    /// returns `Ok(None)` when nothing arrived in time.
+    pub fn next_event(&mut self, timeout_ms: Option<u32>) -> crate::Result<Option<Event>> {
+        loop {
+            while let Some(len) = unit_len(&self.rx) {
+                let event = decode_event(&self.rx[..len]);
+                self.rx.drain(..len);
+                if event.is_some() {
+                    return Ok(event);
+                }
+            }
+            let mut pollfd = [0u8; 8];
+            pollfd[..4].copy_from_slice(&self.fd.to_le_bytes());
+            pollfd[4..6].copy_from_slice(&POLLIN.to_le_bytes());
+            let timeout = timeout_ms.map(|ms| ms.min(i32::MAX as u32) as isize).unwrap_or(-1);
+            let ready =
+                sys::check(unsafe { sys::syscall3(nr::POLL, pollfd.as_mut_ptr() as usize, 1, timeout as usize) })?;
+            if ready == 0 {
+                return Ok(None);
+            }
+            let have = self.rx.len();
+            self.fill_rx(have + 1)?;
+        }
+    }
+}
+
+impl Drop for Display {
+    fn drop(&mut self) {
+        unsafe {
+            sys::syscall1(nr::CLOSE, self.fd as usize);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The reply the ReduxOS server sends (`linux_x11_queue_setup_success`).
+    fn redux_setup_reply() -> Vec<u8> {
+        let mut r = vec![1, 0, 11, 0, 0, 0, 0, 0];
+        r.extend_from_slice(&1u32.to_le_bytes());
+        r.extend_from_slice(&0x0020_0000u32.to_le_bytes());
+        r.extend_from_slice(&0x001F_FFFFu32.to_le_bytes());
+        r.extend_from_slice(&0u32.to_le_bytes());
+        r.extend_from_slice(&5u16.to_le_bytes());
+        r.extend_from_slice(&0xFFFFu16.to_le_bytes());
+        r.extend_from_slice(&[1, 1, 0, 0, 32, 32, 8, 255, 0, 0, 0, 0]);
+        r.extend_from_slice(b"Go OS\0\0\0");
+        r.extend_from_slice(&[24, 32, 32, 0, 0, 0, 0, 0]);
+        let mut screen = [0u8; 40];
+        screen[..4].copy_from_slice(&0x100u32.to_le_bytes());
+        screen[20..22].copy_from_slice(&1280u16.to_le_bytes());
+        screen[22..24].copy_from_slice(&800u16.to_le_bytes());
+        r.extend_from_slice(&screen);
+        let units = ((r.len() - 8) / 4) as u16;
+        r[6..8].copy_from_slice(&units.to_le_bytes());
+        r
+    }
+
+    #[test]
+    fn setup_reply_parses() {
+        let setup = parse_setup(&redux_setup_reply()).unwrap();
+        assert_eq!(setup.root, 0x100);
+        assert_eq!(setup.id_base, 0x0020_0000);
+        assert_eq!((setup.width, setup.height), (1280, 800));
+        assert_eq!(setup.max_request_bytes, 0xFFFF * 4);
+        let mut failed = redux_setup_reply();
+        failed[0] = 0;
+        assert!(parse_setup(&failed).is_err());
+        assert!(parse_setup(&redux_setup_reply()[..60]).is_err());
+    }
+
+    #[test]
+    fn requests_are_padded_and_sized() {
+        let req = Req::new(OP_CHANGE_PROPERTY, 0)
+            .u32(7)
+            .u32(ATOM_WM_NAME)
+            .u32(ATOM_STRING)
+            .u32(8)
+            .u32(3)
+            .bytes(b"abc");
+        let bytes = req.finish();
+        assert_eq!(bytes.len(), 28);
+        assert_eq!(u16_at(&bytes, 2), 7);
+        assert_eq!(&bytes[24..28], b"abc\0");
+    }
+
+    #[test]
+    fn units_and_events() {
+        let mut buf = vec![0u8; 32];
+        buf[0] = 1;
+        buf[4] = 2; // reply with 8 extra bytes
+        assert_eq!(unit_len(&buf), None);
+        buf.extend_from_slice(&[0; 8]);
+        assert_eq!(unit_len(&buf), Some(40));
+
+        let mut button = [0u8; 32];
+        button[0] = 4;
+        button[1] = 1;
+        button[20..22].copy_from_slice(&30u16.to_le_bytes());
+        button[22..24].copy_from_slice(&40u16.to_le_bytes());
+        assert_eq!(
+            decode_event(&button),
+            Some(Event::Button {
+                button: 1,
+                x: 30,
+                y: 40,
+                pressed: true
+            })
+        );
+        let mut configure = [0u8; 32];
+        configure[0] = 22;
+        configure[8..12].copy_from_slice(&0x0020_0001u32.to_le_bytes());
+        configure[16..18].copy_from_slice(&640u16.to_le_bytes());
+        configure[18..20].copy_from_slice(&480u16.to_le_bytes());
+        assert_eq!(
+            decode_event(&configure),
+            Some(Event::Resize {
+                window: 0x0020_0001,
+                width: 640,
+                height: 480
+            })
+        );
+        assert_eq!(key_char(b'q' + 8), Some('q'));
+        assert_eq!(key_char(3), None);
+    }
+}
@@ -0,0 +1,176 @@
+//! Global allocator on anonymous `mmap`.
+//!
+//! Blocks up to 2 KiB come from power-of-two size classes carved out of
+//! 64 KiB chunks and go back to a per-class free list; chunks are never
+//! returned. Bigger blocks get their own mapping and are unmapped on free.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::sys::{self, nr};
+
+const PAGE: usize = 4096;
+const MIN_CLASS_SHIFT: usize = 4;
+const CLASSES: usize = 8; // 16 .. 2048 bytes
+const CHUNK: usize = 64 * 1024;
+
+const PROT_READ: usize = 1;
+const PROT_WRITE: usize = 2;
+const MAP_PRIVATE: usize = 0x02;
+const MAP_ANONYMOUS: usize = 0x20;
+
+struct State {
+    free: [*mut u8; CLASSES],
+    bump: *mut u8,
+    bump_end: *mut u8,
+}
+
+pub struct Heap {
+    locked: AtomicBool,
+    state: core::cell::UnsafeCell<State>,
+}
+
+unsafe impl Sync for Heap {}
+
+impl Heap {
+    pub const fn new() -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            state: core::cell::UnsafeCell::new(State {
+                free: [ptr::null_mut(); CLASSES],
+                bump: ptr::null_mut(),
+                bump_end: ptr::null_mut(),
+            }),
+        }
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut State) -> R) -> R {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+        let result = f(unsafe { &mut *self.state.get() });
+        self.locked.store(false, Ordering::Release);
+        result
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Size class for `layout`, or `None` when it needs its own mapping.
+fn class_of(layout: Layout) -> Option<usize> {
+    let size = layout.size().max(layout.align()).max(1 << MIN_CLASS_SHIFT);
+    let class = size.next_power_of_two().trailing_zeros() as usize - MIN_CLASS_SHIFT;
+    (class < CLASSES).then_some(class)
+}
+
+fn map(len: usize) -> *mut u8 {
+    let ret = unsafe {
+        sys::syscall6(
+            nr::MMAP,
+            0,
+            len,
+            PROT_READ | PROT_WRITE,
+            MAP_PRIVATE | MAP_ANONYMOUS,
+            usize::MAX,
+            0,
+        )
+    };
+    match sys::check(ret) {
+        Ok(addr) => addr as *mut u8,
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+fn large_len(layout: Layout) -> usize {
+    (layout.size() + PAGE - 1) & !(PAGE - 1)
+}
+
+unsafe impl GlobalAlloc for Heap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some(class) = class_of(layout) else {
+            if layout.align() > PAGE {
+                return ptr::null_mut();
+            }
+            return map(large_len(layout));
+        };
+        let size = 1usize << (class + MIN_CLASS_SHIFT);
+        self.with(|state| {
+            let head = state.free[class];
+            if !head.is_null() {
+                state.free[class] = unsafe { *(head as *mut *mut u8) };
+                return head;
+            }
+            // Chunks are page aligned; rounding the bump pointer up to the
+            // block size gives every block its natural alignment.
+            let mut start = ((state.bump as usize + size - 1) & !(size - 1)) as *mut u8;
+            if state.bump.is_null() || start as usize + size > state.bump_end as usize {
+                let chunk = map(CHUNK);
+                if chunk.is_null() {
+                    return ptr::null_mut();
+                }
+                state.bump_end = unsafe { chunk.add(CHUNK) };
+                start = chunk;
+            }
+            state.bump = unsafe { start.add(size) };
+            start
+        })
+    }
+
+    unsafe fn dealloc(&self, block: *mut u8, layout: Layout) {
+        let Some(class) = class_of(layout) else {
+            unsafe {
+                sys::syscall2(nr::MUNMAP, block as usize, large_len(layout));
+            }
+            return;
+        };
+        self.with(|state| unsafe {
+            *(block as *mut *mut u8) = state.free[class];
+            state.free[class] = block;
+        });
+    }
+}
+
+#[cfg(all(feature = "rt", not(test)))]
+#[global_allocator]
+static HEAP: Heap = Heap::new();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classes_cover_size_and_alignment() {
+        assert_eq!(class_of(Layout::from_size_align(1, 1).unwrap()), Some(0));
+        assert_eq!(class_of(Layout::from_size_align(17, 8).unwrap()), Some(1));
+        assert_eq!(class_of(Layout::from_size_align(8, 64).unwrap()), Some(2));
+        assert_eq!(class_of(Layout::from_size_align(2048, 8).unwrap()), Some(7));
+        assert_eq!(class_of(Layout::from_size_align(2049, 8).unwrap()), None);
+    }
+
+    #[test]
+    fn blocks_are_reused_and_aligned() {
+        let heap = Heap::new();
+        let layout = Layout::from_size_align(40, 8).unwrap();
+        unsafe {
+            let a = heap.alloc(layout);
+            let b = heap.alloc(layout);
+            assert!(!a.is_null() && !b.is_null() && a != b);
+            assert_eq!(a as usize % 64, 0);
+            heap.dealloc(a, layout);
+            assert_eq!(heap.alloc(layout), a);
+            let big = Layout::from_size_align(10_000, 16).unwrap();
+            let c = heap.alloc(big);
+            c.write_bytes(0xAA, 10_000);
+            heap.dealloc(c, big);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! HTTP(S) client backed by the kernel `redux_http` service.
+//!
+//! The kernel makes the connection and buffers the response; the app opens
+//! a request, adds headers and options, sends it and reads the body. 3xx
+//! responses come back as they are unless `redirects` asks the kernel to
+//! follow them. An app may hold 8 requests at once and its downloads count
+//! against its network quota, so drop responses when done.
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::sys::{self, Error};
+
+/// Most bytes of body one send carries (`REDUX_HTTP_MAX_BODY`).
+pub const MAX_BODY: usize = 1024 * 1024;
+
+const OPTION_KEEP_ALIVE_MS: usize = 1;
+const OPTION_NO_DELAY: usize = 2;
+const OPTION_TIMEOUT_MS: usize = 3;
+const OPTION_REDIRECTS: usize = 4;
+
+fn close(handle: u64) {
+    let _ = unsafe { sys::native(sys::native::HTTP_CLOSE, handle as usize, 0, 0, 0) };
+}
+
+/// A request not sent yet.
+#[derive(Debug)]
+pub struct Request {
+    handle: u64,
+}
+
+impl Request {
+    pub fn new(method: &str, url: &str) -> crate::Result<Request> {
+        let handle = unsafe {
+            sys::native(
+                sys::native::HTTP_OPEN,
+                method.as_ptr() as usize,
+                method.len(),
+                url.as_ptr() as usize,
+                url.len(),
+            )?
+        };
+        Ok(Request { handle })
+    }
+
+    pub fn header(&mut self, name: &str, value: &str) -> crate::Result<&mut Request> {
+        let line = alloc::format!("{}: {}", name, value);
+        unsafe {
+            sys::native(
+                sys::native::HTTP_SET_HEADER,
+                self.handle as usize,
+                line.as_ptr() as usize,
+                line.len(),
+                0,
+            )?;
+        }
+        Ok(self)
+    }
+
+    fn option(&mut self, option: usize, value: u64) -> crate::Result<&mut Request> {
+        unsafe {
+            sys::native(
+                sys::native::HTTP_SET_OPTION,
+                self.handle as usize,
+                option,
+                value as usize,
+                0,
+            )?;
+        }
+        Ok(self)
+    }
+
+    pub fn timeout_ms(&mut self, ms: u64) -> crate::Result<&mut Request> {
+        self.option(OPTION_TIMEOUT_MS, ms)
+    }
+
+    pub fn keep_alive_ms(&mut self, ms: u64) -> crate::Result<&mut Request> {
+        self.option(OPTION_KEEP_ALIVE_MS, ms)
+    }
+
+    /// Redirects to follow, up to 10.
+    pub fn redirects(&mut self, count: u64) -> crate::Result<&mut Request> {
+        self.option(OPTION_REDIRECTS, count)
+    }
+
+    pub fn no_delay(&mut self, on: bool) -> crate::Result<&mut Request> {
+        self.option(OPTION_NO_DELAY, on as u64)
+    }
+
+    /// Sends the request with `body` and waits for the response.
+    pub fn send(self, body: &[u8]) -> crate::Result<Response> {
+        if body.len() > MAX_BODY {
+            return Err(Error::Invalid);
+        }
+        let status = unsafe {
+            sys::native(
+                sys::native::HTTP_SEND,
+                self.handle as usize,
+                body.as_ptr() as usize,
+                body.len(),
+                0,
+            )?
+        };
+        let handle = self.handle;
+        core::mem::forget(self);
+        Ok(Response {
+            handle,
+            status: status as u16,
+        })
+    }
+}
+
+impl Drop for Request {
+    fn drop(&mut self) {
+        close(self.handle);
+    }
+}
+
+#[derive(Debug)]
+pub struct Response {
+    handle: u64,
+    pub status: u16,
+}
+
+impl Response {
+    /// Response headers, names in lowercase.
+    pub fn headers(&self) -> crate::Result<Vec<(String, String)>> {
+        let mut buf = vec![0u8; 1024];
+        loop {
+            let len = unsafe {
+                sys::native(
+                    sys::native::HTTP_HEADERS,
+                    self.handle as usize,
+                    buf.as_mut_ptr() as usize,
+                    buf.len(),
+                    0,
+                )?
+            } as usize;
+            if len <= buf.len() {
+                return Ok(parse_header_lines(&String::from_utf8_lossy(&buf[..len])));
+            }
+            buf.resize(len, 0);
+        }
+    }
+
+    pub fn header(&self, name: &str) -> crate::Result<Option<String>> {
+        Ok(self
+            .headers()?
+            .into_iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value))
+    }
+
+    /// Next part of the body; `Ok(0)` at the end.
+    pub fn read(&mut self, buf: &mut [u8]) -> crate::Result<usize> {
+        let n = unsafe {
+            sys::native(
+                sys::native::HTTP_READ,
+                self.handle as usize,
+                buf.as_mut_ptr() as usize,
+                buf.len(),
+                0,
+            )?
+        };
+        Ok(n as usize)
+    }
+
+    pub fn bytes(mut self) -> crate::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        let mut chunk = vec![0u8; 16 * 1024];
+        loop {
+            let n = self.read(&mut chunk)?;
+            if n == 0 {
+                return Ok(out);
+            }
+            out.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    pub fn text(self) -> crate::Result<String> {
+        Ok(String::from_utf8_lossy(&self.bytes()?).into_owned())
+    }
+}
+
+impl Drop for Response {
+    fn drop(&mut self) {
+        close(self.handle);
+    }
+}
+
+pub fn get(url: &str) -> crate::Result<Response> {
+    Request::new("GET", url)?.send(&[])
+}
+
+pub fn post(url: &str, content_type: &str, body: &[u8]) -> crate::Result<Response> {
+    let mut request = Request::new("POST", url)?;
+    request.header("Content-Type", content_type)?;
+    request.send(body)
+}
+
+/// `name: value` lines as the kernel returns them.
+fn parse_header_lines(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), String::from(value.trim())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_lines_split_on_first_colon() {
+        let headers = parse_header_lines("Content-Type: text/html\nlocation: http://a:8080/x\n\nbroken\n");
+        assert_eq!(
+            headers,
+            [
+                ("content-type".into(), "text/html".into()),
+                ("location".into(), "http://a:8080/x".into()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,72 @@
+//! Standard streams and the `print!` family.
+
+use core::fmt;
+
+use crate::sys::{self, nr};
+
+pub const STDIN: i32 = 0;
+pub const STDOUT: i32 = 1;
+pub const STDERR: i32 = 2;
+
+/// Reads once from `fd`; `Ok(0)` at end of file.
+pub fn read(fd: i32, buf: &mut [u8]) -> crate::Result<usize> {
+    loop {
+        let ret = unsafe { sys::syscall3(nr::READ, fd as usize, buf.as_mut_ptr() as usize, buf.len()) };
+        match sys::check(ret) {
+            Err(sys::Error::Errno(sys::EINTR)) => continue,
+            other => return other,
+        }
+    }
+}
+
+/// Writes all of `data` to `fd`.
+pub fn write_all(fd: i32, mut data: &[u8]) -> crate::Result<()> {
+    while !data.is_empty() {
+        let ret = unsafe { sys::syscall3(nr::WRITE, fd as usize, data.as_ptr() as usize, data.len()) };
+        match sys::check(ret) {
+            Ok(0) => return Err(sys::Error::Errno(sys::EAGAIN)),
+            Ok(n) => data = &data[n..],
+            Err(sys::Error::Errno(sys::EINTR)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// A file descriptor as a `fmt::Write` sink.
+pub struct Stream(pub i32);
+
+impl fmt::Write for Stream {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        write_all(self.0, s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+#[doc(hidden)]
+pub fn print_to(fd: i32, args: fmt::Arguments) {
+    let _ = fmt::Write::write_fmt(&mut Stream(fd), args);
+}
+
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => {
+        $crate::io::print_to($crate::io::STDOUT, format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! println {
+    () => {
+        $crate::print!("\n")
+    };
+    ($($arg:tt)*) => {
+        $crate::io::print_to($crate::io::STDOUT, format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+#[macro_export]
+macro_rules! eprintln {
+    ($($arg:tt)*) => {
+        $crate::io::print_to($crate::io::STDERR, format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
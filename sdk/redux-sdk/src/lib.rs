@@ -0,0 +1,48 @@
+//! Rust SDK for ReduxOS userspace apps.
+//!
+//! A ReduxOS app is a static x86_64 ELF that the kernel runs under its Linux
+//! ABI shim (`linux runloop start /APP.BIN`). Files, sockets, memory and time
+//! go through the Linux syscall numbers; the ReduxOS services (HTTP client,
+//! config registry, folder watches, random bytes) are native calls the shim
+//! exposes at `sys::native::BASE + id`. The GUI is the X11 subset the kernel
+//! serves on display `:0`.
+//!
+//! ```text
+//! #![no_std]
+//! #![no_main]
+//!
+//! use redux_sdk::println;
+//!
+//! redux_sdk::main!(main);
+//!
+//! fn main() {
+//!     println!("hola desde ReduxOS");
+//! }
+//! ```
+//!
+//! `cargo redux build` (the `cargo-redux` helper next to this crate) builds
+//! for `x86_64-redux.json` and checks the ELF profile the loader accepts.
+//! The same app also builds for `x86_64-unknown-linux-gnu` and runs on a
+//! Linux host, where the native calls fail with `Error::Errno(ENOSYS)`.
+//!
+//! The `rt` feature (on by default) provides the entry point, the panic
+//! handler and a global allocator.
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+pub mod config;
+pub mod fs;
+pub mod gui;
+pub mod heap;
+pub mod http;
+pub mod io;
+pub mod random;
+pub mod rt;
+pub mod sys;
+pub mod time;
+
+pub use sys::Error;
+
+pub type Result<T> = core::result::Result<T, Error>;
@@ -0,0 +1,22 @@
+//! Random bytes from the kernel generator.
+
+use crate::sys;
+
+/// Most bytes one native call fills (`SYS_GET_RANDOM_MAX`).
+const CALL_MAX: usize = 4096;
+
+pub fn fill(buf: &mut [u8]) -> crate::Result<()> {
+    for part in buf.chunks_mut(CALL_MAX) {
+        let n = unsafe { sys::native(sys::native::GET_RANDOM, part.as_mut_ptr() as usize, part.len(), 0, 0)? };
+        if n as usize != part.len() {
+            return Err(sys::Error::Invalid);
+        }
+    }
+    Ok(())
+}
+
+pub fn u64() -> crate::Result<u64> {
+    let mut bytes = [0u8; 8];
+    fill(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
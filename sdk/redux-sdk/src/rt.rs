@@ -0,0 +1,157 @@
+//! Process entry, arguments and the panic handler.
+//!
+//! On ReduxOS (`target_os = "none"`) `_start` reads argc/argv/envp from the
+//! initial stack the Linux shim builds. On a Linux host the C runtime calls
+//! `main` instead. Either way the app's function, registered with
+//! [`main!`](crate::main), runs once and its result becomes the exit code.
+
+use core::ffi::CStr;
+use core::fmt;
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+static ARGC: AtomicUsize = AtomicUsize::new(0);
+static ARGV: AtomicPtr<*const u8> = AtomicPtr::new(core::ptr::null_mut());
+static ENVP: AtomicPtr<*const u8> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Registers `path` as the app entry point. It may return `()` or
+/// `Result<(), E>` with `E: Display`.
+#[macro_export]
+macro_rules! main {
+    ($path:path) => {
+        #[unsafe(no_mangle)]
+        pub extern "C" fn redux_sdk_main() -> i32 {
+            $crate::rt::Termination::report($path())
+        }
+    };
+}
+
+/// What an app entry point may return.
+pub trait Termination {
+    fn report(self) -> i32;
+}
+
+impl Termination for () {
+    fn report(self) -> i32 {
+        0
+    }
+}
+
+impl<E: fmt::Display> Termination for Result<(), E> {
+    fn report(self) -> i32 {
+        match self {
+            Ok(()) => 0,
+            Err(error) => {
+                crate::eprintln!("error: {}", error);
+                1
+            }
+        }
+    }
+}
+
+/// Saves the process arguments; the runtime calls this before the app.
+///
+/// # Safety
+/// `argv` and `envp` must be null-terminated arrays of C strings that live
+/// for the rest of the process.
+pub unsafe fn init(argc: usize, argv: *const *const u8, envp: *const *const u8) {
+    ARGC.store(argc, Ordering::Relaxed);
+    ARGV.store(argv as *mut _, Ordering::Relaxed);
+    ENVP.store(envp as *mut _, Ordering::Relaxed);
+}
+
+fn c_str(ptr: *const u8) -> &'static str {
+    unsafe { CStr::from_ptr(ptr as *const core::ffi::c_char) }
+        .to_str()
+        .unwrap_or("")
+}
+
+/// Command line arguments, program name first.
+pub fn args() -> impl Iterator<Item = &'static str> {
+    let argv = ARGV.load(Ordering::Relaxed);
+    let argc = if argv.is_null() {
+        0
+    } else {
+        ARGC.load(Ordering::Relaxed)
+    };
+    (0..argc).map(move |i| c_str(unsafe { *argv.add(i) }))
+}
+
+/// Value of environment variable `key`.
+pub fn env(key: &str) -> Option<&'static str> {
+    let envp = ENVP.load(Ordering::Relaxed);
+    if envp.is_null() {
+        return None;
+    }
+    let mut i = 0;
+    loop {
+        let entry = unsafe { *envp.add(i) };
+        if entry.is_null() {
+            return None;
+        }
+        if let Some((name, value)) = c_str(entry).split_once('=') {
+            if name == key {
+                return Some(value);
+            }
+        }
+        i += 1;
+    }
+}
+
+#[cfg(all(feature = "rt", not(test)))]
+unsafe extern "C" {
+    fn redux_sdk_main() -> i32;
+}
+
+#[cfg(all(feature = "rt", not(test), target_os = "none"))]
+core::arch::global_asm!(
+    r#"
+.global _start
+_start:
+    xor rbp, rbp
+    mov rdi, rsp
+    and rsp, -16
+    call redux_sdk_start
+    ud2
+"#
+);
+
+#[cfg(all(feature = "rt", not(test), target_os = "none"))]
+#[unsafe(no_mangle)]
+extern "C" fn redux_sdk_start(stack: *const usize) -> ! {
+    // [argc][argv...][null][envp...][null]
+    unsafe {
+        let argc = *stack;
+        let argv = stack.add(1) as *const *const u8;
+        init(argc, argv, argv.add(argc + 1));
+        crate::sys::exit(redux_sdk_main())
+    }
+}
+
+#[cfg(all(feature = "rt", not(test), target_os = "linux"))]
+#[unsafe(no_mangle)]
+extern "C" fn main(argc: i32, argv: *const *const u8, envp: *const *const u8) -> i32 {
+    unsafe {
+        init(argc.max(0) as usize, argv, envp);
+        redux_sdk_main()
+    }
+}
+
+// A `no_std` binary links with `-nodefaultlibs`; on a Linux host the C
+// runtime, `memcpy` and `strlen` come from libc. The host's prebuilt `alloc`
+// still refers to the unwinder (`_Unwind_Resume` from libgcc_s and the
+// personality routine below) even with `panic = "abort"`; neither runs.
+#[cfg(all(feature = "rt", not(test), target_os = "linux"))]
+#[link(name = "c")]
+#[link(name = "gcc_s")]
+unsafe extern "C" {}
+
+#[cfg(all(feature = "rt", not(test), target_os = "linux"))]
+#[unsafe(no_mangle)]
+extern "C" fn rust_eh_personality() {}
+
+#[cfg(all(feature = "rt", not(test)))]
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    crate::eprintln!("panic: {}", info);
+    crate::sys::exit(101)
+}
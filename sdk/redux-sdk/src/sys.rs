@@ -0,0 +1,216 @@
+//! Raw syscalls and error codes.
+//!
+//! `syscall0`..`syscall6` issue the x86_64 `syscall` instruction with the
+//! Linux register convention. Linux calls return `-errno` on failure; native
+//! calls return the kernel `SYS_ERR_*` values, which are near `u64::MAX`.
+
+use core::arch::asm;
+use core::fmt;
+
+/// Linux syscall numbers the SDK uses.
+pub mod nr {
+    pub const READ: usize = 0;
+    pub const WRITE: usize = 1;
+    pub const CLOSE: usize = 3;
+    pub const FSTAT: usize = 5;
+    pub const POLL: usize = 7;
+    pub const LSEEK: usize = 8;
+    pub const MMAP: usize = 9;
+    pub const MUNMAP: usize = 11;
+    pub const NANOSLEEP: usize = 35;
+    pub const SOCKET: usize = 41;
+    pub const CONNECT: usize = 42;
+    pub const EXIT: usize = 60;
+    pub const GETDENTS64: usize = 217;
+    pub const CLOCK_GETTIME: usize = 228;
+    pub const EXIT_GROUP: usize = 231;
+    pub const OPENAT: usize = 257;
+    pub const NEWFSTATAT: usize = 262;
+}
+
+/// ReduxOS native calls, as numbered in `kernel/src/syscall.rs`.
+pub mod native {
+    /// Offset the Linux shim adds in front of the native ids.
+    pub const BASE: usize = 0x5200;
+
+    pub const GET_TICK: usize = 2;
+    pub const HTTP_OPEN: usize = 10;
+    pub const HTTP_SET_HEADER: usize = 11;
+    pub const HTTP_SEND: usize = 12;
+    pub const HTTP_HEADERS: usize = 13;
+    pub const HTTP_READ: usize = 14;
+    pub const HTTP_CLOSE: usize = 15;
+    pub const CONFIG_GET: usize = 16;
+    pub const CONFIG_SET: usize = 17;
+    pub const CONFIG_WATCH: usize = 18;
+    pub const HTTP_SET_OPTION: usize = 19;
+    pub const GET_RANDOM: usize = 20;
+    pub const FS_WATCH: usize = 21;
+    pub const FS_READ_EVENTS: usize = 22;
+    pub const FS_UNWATCH: usize = 23;
+}
+
+pub const ENOENT: i32 = 2;
+pub const EINTR: i32 = 4;
+pub const EBADF: i32 = 9;
+pub const EAGAIN: i32 = 11;
+pub const EINVAL: i32 = 22;
+pub const ENOSYS: i32 = 38;
+pub const ECONNREFUSED: i32 = 111;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A Linux call failed with this errno.
+    Errno(i32),
+    BadSyscall,
+    BadThread,
+    Permission,
+    Network,
+    Quota,
+    Denied,
+    BadHandle,
+    Unsupported,
+    Invalid,
+    NotFound,
+    /// The peer sent something the SDK could not decode.
+    Protocol,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Errno(code) => write!(f, "errno {}", code),
+            Error::BadSyscall => f.write_str("llamada desconocida"),
+            Error::BadThread => f.write_str("hilo invalido"),
+            Error::Permission => f.write_str("sin permiso"),
+            Error::Network => f.write_str("error de red"),
+            Error::Quota => f.write_str("cuota agotada"),
+            Error::Denied => f.write_str("denegado"),
+            Error::BadHandle => f.write_str("handle invalido"),
+            Error::Unsupported => f.write_str("no soportado"),
+            Error::Invalid => f.write_str("argumento invalido"),
+            Error::NotFound => f.write_str("no encontrado"),
+            Error::Protocol => f.write_str("respuesta mal formada"),
+        }
+    }
+}
+
+/// # Safety
+/// Pointer arguments must be valid for whatever call `n` reads or writes
+/// through them.
+#[inline]
+pub unsafe fn syscall6(n: usize, a0: usize, a1: usize, a2: usize, a3: usize, a4: usize, a5: usize) -> isize {
+    let ret: isize;
+    unsafe {
+        asm!(
+            "syscall",
+            inlateout("rax") n as isize => ret,
+            in("rdi") a0,
+            in("rsi") a1,
+            in("rdx") a2,
+            in("r10") a3,
+            in("r8") a4,
+            in("r9") a5,
+            lateout("rcx") _,
+            lateout("r11") _,
+            options(nostack),
+        );
+    }
+    ret
+}
+
+/// # Safety
+/// As for [`syscall6`].
+#[inline]
+pub unsafe fn syscall0(n: usize) -> isize {
+    unsafe { syscall6(n, 0, 0, 0, 0, 0, 0) }
+}
+
+/// # Safety
+/// As for [`syscall6`].
+#[inline]
+pub unsafe fn syscall1(n: usize, a0: usize) -> isize {
+    unsafe { syscall6(n, a0, 0, 0, 0, 0, 0) }
+}
+
+/// # Safety
+/// As for [`syscall6`].
+#[inline]
+pub unsafe fn syscall2(n: usize, a0: usize, a1: usize) -> isize {
+    unsafe { syscall6(n, a0, a1, 0, 0, 0, 0) }
+}
+
+/// # Safety
+/// As for [`syscall6`].
+#[inline]
+pub unsafe fn syscall3(n: usize, a0: usize, a1: usize, a2: usize) -> isize {
+    unsafe { syscall6(n, a0, a1, a2, 0, 0, 0) }
+}
+
+/// # Safety
+/// As for [`syscall6`].
+#[inline]
+pub unsafe fn syscall4(n: usize, a0: usize, a1: usize, a2: usize, a3: usize) -> isize {
+    unsafe { syscall6(n, a0, a1, a2, a3, 0, 0) }
+}
+
+/// Result of a Linux call: the value, or `Errno` for -4095..=-1.
+pub fn check(ret: isize) -> crate::Result<usize> {
+    if (-4095..0).contains(&ret) {
+        Err(Error::Errno(-ret as i32))
+    } else {
+        Ok(ret as usize)
+    }
+}
+
+/// Native call `id` with up to four arguments.
+///
+/// # Safety
+/// As for [`syscall6`].
+pub unsafe fn native(id: usize, a0: usize, a1: usize, a2: usize, a3: usize) -> crate::Result<u64> {
+    check_native(unsafe { syscall4(native::BASE + id, a0, a1, a2, a3) } as u64)
+}
+
+/// Decodes a native return value. A kernel without the native gate answers
+/// `-ENOSYS`, which is passed through as `Errno`.
+pub fn check_native(ret: u64) -> crate::Result<u64> {
+    let error = match u64::MAX - ret {
+        1 => Error::BadSyscall,
+        2 => Error::BadThread,
+        3 => Error::Permission,
+        4 => Error::Network,
+        5 => Error::Quota,
+        6 => Error::Denied,
+        7 => Error::BadHandle,
+        8 => Error::Unsupported,
+        9 => Error::Invalid,
+        10 => Error::NotFound,
+        _ => return check(ret as isize).map(|value| value as u64),
+    };
+    Err(error)
+}
+
+pub fn exit(code: i32) -> ! {
+    unsafe {
+        syscall1(nr::EXIT_GROUP, code as usize);
+        syscall1(nr::EXIT, code as usize);
+    }
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn native_errors_decode() {
+        assert_eq!(check_native(u64::MAX - 7), Err(Error::BadHandle));
+        assert_eq!(check_native(u64::MAX - 10), Err(Error::NotFound));
+        assert_eq!(check_native(200), Ok(200));
+        assert_eq!(check_native(-(ENOSYS as i64) as u64), Err(Error::Errno(ENOSYS)));
+        assert_eq!(check(-(ENOENT as isize)), Err(Error::Errno(ENOENT)));
+        assert_eq!(check(3), Ok(3));
+    }
+}
@@ -0,0 +1,39 @@
+//! Clocks and sleeping.
+
+use crate::sys::{self, nr};
+
+const CLOCK_MONOTONIC: usize = 1;
+
+#[repr(C)]
+#[derive(Default)]
+struct Timespec {
+    sec: i64,
+    nsec: i64,
+}
+
+/// Milliseconds on the monotonic clock.
+pub fn now_ms() -> u64 {
+    let mut ts = Timespec::default();
+    let ret = unsafe { sys::syscall2(nr::CLOCK_GETTIME, CLOCK_MONOTONIC, &mut ts as *mut Timespec as usize) };
+    if sys::check(ret).is_err() {
+        return 0;
+    }
+    (ts.sec as u64)
+        .saturating_mul(1000)
+        .saturating_add(ts.nsec as u64 / 1_000_000)
+}
+
+pub fn sleep_ms(ms: u64) {
+    let ts = Timespec {
+        sec: (ms / 1000) as i64,
+        nsec: ((ms % 1000) * 1_000_000) as i64,
+    };
+    unsafe {
+        sys::syscall2(nr::NANOSLEEP, &ts as *const Timespec as usize, 0);
+    }
+}
+
+/// Kernel timer ticks since boot (native call).
+pub fn ticks() -> crate::Result<u64> {
+    unsafe { sys::native(sys::native::GET_TICK, 0, 0, 0, 0) }
+}
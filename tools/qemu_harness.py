@@ -0,0 +1,100 @@
+#!/usr/bin/env python3
+"""Client for the kernel test harness (kernel/src/testharness.rs).
+
+Connects to the COM2 command channel exposed by scripts/run_qemu_tests.sh,
+runs every command of a spec file and checks the expected substrings.
+Spec lines look like `<command> => <expected substring>`; `#` starts a comment.
+"""
+import socket
+import sys
+import time
+
+
+def read_frame(sock, buf, deadline):
+    while b"\n" not in buf[0]:
+        remaining = deadline - time.time()
+        if remaining <= 0:
+            raise TimeoutError("harness did not answer in time")
+        sock.settimeout(remaining)
+        chunk = sock.recv(4096)
+        if not chunk:
+            raise ConnectionError("harness channel closed")
+        buf[0] += chunk
+    line, buf[0] = buf[0].split(b"\n", 1)
+    return line.decode("utf-8", "replace").rstrip("\r")
+
+
+def wait_for(sock, buf, prefix, timeout):
+    deadline = time.time() + timeout
+    while True:
+        frame = read_frame(sock, buf, deadline)
+        if frame.startswith(prefix):
+            return frame
+
+
+def run_command(sock, buf, cmd_id, command, timeout):
+    sock.sendall(f"CMD {cmd_id} {command}\n".encode())
+    wait_for(sock, buf, f"@@BEGIN {cmd_id}", timeout)
+    out = []
+    deadline = time.time() + timeout
+    while True:
+        frame = read_frame(sock, buf, deadline)
+        if frame.startswith(f"@@OUT {cmd_id} "):
+            out.append(frame[len(f"@@OUT {cmd_id} "):])
+        elif frame.startswith(f"@@END {cmd_id}"):
+            return out
+
+
+def load_spec(path):
+    cases = []
+    with open(path, encoding="utf-8") as fh:
+        for raw in fh:
+            line = raw.strip()
+            if not line or line.startswith("#"):
+                continue
+            command, _, expected = line.partition("=>")
+            cases.append((command.strip(), expected.strip()))
+    return cases
+
+
+def main():
+    if len(sys.argv) < 3:
+        print("Uso: qemu_harness.py <host:puerto> <spec.txt> [timeout_s]")
+        return 2
+    host, port = sys.argv[1].rsplit(":", 1)
+    cases = load_spec(sys.argv[2])
+    timeout = float(sys.argv[3]) if len(sys.argv) > 3 else 120.0
+
+    deadline = time.time() + timeout
+    while True:
+        try:
+            sock = socket.create_connection((host, int(port)), timeout=5)
+            break
+        except OSError:
+            if time.time() > deadline:
+                print("[error] no se pudo conectar al canal de comandos")
+                return 2
+            time.sleep(0.5)
+
+    buf = [b""]
+    sock.sendall(b"PING\n")
+    wait_for(sock, buf, "@@PONG", timeout)
+
+    failures = 0
+    for index, (command, expected) in enumerate(cases, start=1):
+        output = run_command(sock, buf, index, command, 30.0)
+        ok = any(expected in line for line in output)
+        print(f"[{'PASS' if ok else 'FAIL'}] {command!r} => {expected!r}")
+        if not ok:
+            failures += 1
+            for line in output[-20:]:
+                print(f"    | {line}")
+
+    sock.sendall(f"EXIT {1 if failures else 0}\n".encode())
+    sock.close()
+    print(f"{len(cases) - failures}/{len(cases)} casos OK")
+    return 1 if failures else 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())